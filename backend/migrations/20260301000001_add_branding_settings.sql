@@ -0,0 +1,101 @@
+-- Migration: Add White-Label Branding Settings
+-- Date: 2026-03-01
+--
+-- Stores practice-level theming metadata (colors and logo/email header asset
+-- references) in system_settings under the 'branding' group. Image assets are
+-- stored in uploaded_files with the new BRANDING purpose and referenced here
+-- by file ID. All values are public: they are served unauthenticated by
+-- GET /api/v1/branding so the login page can be themed.
+
+-- Add BRANDING purpose for theming image assets
+ALTER TYPE file_purpose ADD VALUE IF NOT EXISTS 'BRANDING';
+
+INSERT INTO system_settings (
+    setting_key, setting_group, setting_name, setting_value,
+    value_type, description, default_value, is_public, is_encrypted, is_readonly
+) VALUES
+(
+    'branding.primary_color',
+    'branding',
+    'Primary Color',
+    '"#2563eb"',
+    'STRING',
+    'Primary UI color as a hex value (#RRGGBB). Used by the frontend theme and email headers.',
+    '"#2563eb"',
+    true,
+    false,
+    false
+),
+(
+    'branding.document_accent_color',
+    'branding',
+    'Document Accent Color',
+    '"#1e40af"',
+    'STRING',
+    'Accent color as a hex value (#RRGGBB) for headings and rules in generated documents.',
+    '"#1e40af"',
+    true,
+    false,
+    false
+),
+(
+    'branding.logo_light_file_id',
+    'branding',
+    'Logo (Light Background)',
+    '""',
+    'STRING',
+    'Uploaded file ID of the logo variant for light backgrounds. Empty to fall back to the practice logo.',
+    '""',
+    true,
+    false,
+    false
+),
+(
+    'branding.logo_dark_file_id',
+    'branding',
+    'Logo (Dark Background)',
+    '""',
+    'STRING',
+    'Uploaded file ID of the logo variant for dark backgrounds.',
+    '""',
+    true,
+    false,
+    false
+),
+(
+    'branding.logo_compact_file_id',
+    'branding',
+    'Logo (Compact)',
+    '""',
+    'STRING',
+    'Uploaded file ID of the compact/square logo variant (sidebar, favicon).',
+    '""',
+    true,
+    false,
+    false
+),
+(
+    'branding.email_header_file_id',
+    'branding',
+    'Email Header Image',
+    '""',
+    'STRING',
+    'Uploaded file ID of the banner image shown at the top of outgoing emails.',
+    '""',
+    true,
+    false,
+    false
+),
+(
+    'branding.public_base_url',
+    'branding',
+    'Public Base URL',
+    '""',
+    'STRING',
+    'Externally reachable base URL of the API (e.g. https://studio.example.com). Required to embed branding images in emails.',
+    '""',
+    true,
+    false,
+    false
+)
+ON CONFLICT (setting_key) DO NOTHING;
@@ -0,0 +1,22 @@
+-- Migration: Add Correlation ID to Notification Queue
+-- Date: 2026-03-02
+--
+-- Links queued notifications to the HTTP request that created them so that a
+-- request ID from a support ticket can be traced across the async boundary to
+-- the scheduler run that eventually delivers (or fails) the notification.
+--
+-- The API sets the transaction-local 'app.request_id' alongside the RLS
+-- context; the column default picks it up so existing INSERT statements do
+-- not need to change. Rows created outside a request (scheduler reminders)
+-- are left NULL.
+
+ALTER TABLE notification_queue
+    ADD COLUMN IF NOT EXISTS correlation_id UUID
+    DEFAULT NULLIF(current_setting('app.request_id', true), '')::UUID;
+
+CREATE INDEX IF NOT EXISTS idx_notification_queue_correlation_id
+    ON notification_queue (correlation_id)
+    WHERE correlation_id IS NOT NULL;
+
+COMMENT ON COLUMN notification_queue.correlation_id IS
+    'Request ID (X-Request-Id) of the API call that queued this notification';
@@ -0,0 +1,46 @@
+-- Migration: Notification Worker Claim Locking
+-- Date: 2026-03-03
+--
+-- Allows several API instances to process the notification queue at the same
+-- time without double-sending:
+-- - Workers claim rows with FOR UPDATE SKIP LOCKED and move them to PROCESSING
+--   under a time-limited lease (claimed_by / lease_expires_at)
+-- - A running worker extends its leases on every heartbeat
+-- - PROCESSING rows whose lease has expired (worker crashed mid-send) become
+--   claimable again
+--
+-- notification_workers records the heartbeat of each worker process so that
+-- operators can see which instances are alive.
+
+ALTER TABLE notification_queue
+    ADD COLUMN IF NOT EXISTS claimed_by VARCHAR(255),
+    ADD COLUMN IF NOT EXISTS claimed_at TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS lease_expires_at TIMESTAMPTZ;
+
+-- Expired-lease recovery scan
+CREATE INDEX IF NOT EXISTS idx_notification_queue_processing_lease
+    ON notification_queue (lease_expires_at)
+    WHERE status = 'PROCESSING';
+
+-- Heartbeat lease extension
+CREATE INDEX IF NOT EXISTS idx_notification_queue_claimed_by
+    ON notification_queue (claimed_by)
+    WHERE status = 'PROCESSING';
+
+COMMENT ON COLUMN notification_queue.claimed_by IS 'Worker ID currently holding the processing lease';
+COMMENT ON COLUMN notification_queue.claimed_at IS 'When the current worker claimed the notification';
+COMMENT ON COLUMN notification_queue.lease_expires_at IS 'Processing lease expiry; expired PROCESSING rows can be reclaimed';
+
+CREATE TABLE IF NOT EXISTS notification_workers (
+    worker_id VARCHAR(255) PRIMARY KEY,
+    hostname VARCHAR(255),
+    started_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_heartbeat_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_notification_workers_heartbeat
+    ON notification_workers (last_heartbeat_at DESC);
+
+GRANT SELECT, INSERT, UPDATE, DELETE ON notification_workers TO mpms_user;
+
+COMMENT ON TABLE notification_workers IS 'Heartbeats of notification queue worker processes';
@@ -0,0 +1,69 @@
+-- Migration: User Invitations
+-- Date: 2026-03-04
+--
+-- Admins invite new staff by email with a pre-assigned role. The invitee opens
+-- a tokenized link, chooses a username and password and enrolls MFA; the user
+-- account is only created at that point. Only the SHA-256 hash of the token is
+-- stored, so a database leak does not expose usable invitation links.
+
+CREATE TABLE IF NOT EXISTS user_invitations (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+
+    -- Invitee details (copied to the user account on acceptance)
+    email VARCHAR(255) NOT NULL,
+    role VARCHAR(20) NOT NULL CHECK (role IN ('ADMIN', 'DOCTOR')),
+    first_name VARCHAR(100) NOT NULL,
+    last_name VARCHAR(100) NOT NULL,
+    phone VARCHAR(20),
+
+    -- SHA-256 (hex) of the invitation token
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    expires_at TIMESTAMPTZ NOT NULL,
+
+    -- Lifecycle
+    accepted_at TIMESTAMPTZ,
+    accepted_user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    revoked_at TIMESTAMPTZ,
+
+    -- Audit fields
+    invited_by UUID NOT NULL REFERENCES users(id) ON DELETE RESTRICT,
+    created_at TIMESTAMPTZ DEFAULT NOW() NOT NULL,
+    updated_at TIMESTAMPTZ DEFAULT NOW() NOT NULL,
+
+    CONSTRAINT invitation_single_outcome CHECK (accepted_at IS NULL OR revoked_at IS NULL)
+);
+
+-- At most one open invitation per email address
+CREATE UNIQUE INDEX IF NOT EXISTS idx_user_invitations_open_email
+    ON user_invitations (LOWER(email))
+    WHERE accepted_at IS NULL AND revoked_at IS NULL;
+
+CREATE INDEX IF NOT EXISTS idx_user_invitations_created_at
+    ON user_invitations (created_at DESC);
+
+CREATE TRIGGER update_user_invitations_updated_at
+    BEFORE UPDATE ON user_invitations
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
+
+GRANT SELECT, INSERT, UPDATE ON user_invitations TO mpms_user;
+
+INSERT INTO system_settings (
+    setting_key, setting_group, setting_name, setting_value,
+    value_type, description, default_value, is_public, is_encrypted, is_readonly
+) VALUES (
+    'security.invitation_expiry_hours',
+    'security',
+    'Invitation Link Validity (hours)',
+    '72',
+    'INTEGER',
+    'How long a user invitation link remains valid before it must be re-sent.',
+    '72',
+    false,
+    false,
+    false
+)
+ON CONFLICT (setting_key) DO NOTHING;
+
+COMMENT ON TABLE user_invitations IS 'Pending and historical staff invitations (account created on acceptance)';
+COMMENT ON COLUMN user_invitations.token_hash IS 'SHA-256 hex digest of the invitation token (token itself is never stored)';
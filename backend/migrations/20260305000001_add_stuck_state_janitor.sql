@@ -0,0 +1,151 @@
+-- Migration: Stuck-State Janitor
+-- Date: 2026-03-05
+--
+-- A periodic janitor task detects rows that are stuck in transient states and
+-- moves them to FAILED with a diagnostic error:
+-- - notification_queue rows left in PROCESSING (worker died and nobody
+--   reclaimed them)
+-- - generated_documents rows left in GENERATING, or GENERATED rows whose PDF
+--   is missing from document storage
+--
+-- Each recovery is recorded in system_alerts so that operators can see it in
+-- the admin UI and the detailed health check.
+
+-- ============================================================================
+-- System alerts
+-- ============================================================================
+
+CREATE TABLE IF NOT EXISTS system_alerts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    alert_type VARCHAR(50) NOT NULL,
+    severity VARCHAR(20) NOT NULL DEFAULT 'WARNING',
+    source VARCHAR(50) NOT NULL,
+    entity_type VARCHAR(50),
+    entity_id UUID,
+    message TEXT NOT NULL,
+    details JSONB,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    acknowledged_at TIMESTAMPTZ,
+    acknowledged_by UUID REFERENCES users(id) ON DELETE SET NULL,
+
+    CONSTRAINT system_alerts_severity_check CHECK (
+        severity IN ('INFO', 'WARNING', 'CRITICAL')
+    ),
+    CONSTRAINT system_alerts_acknowledged_check CHECK (
+        (acknowledged_at IS NULL AND acknowledged_by IS NULL)
+        OR acknowledged_at IS NOT NULL
+    )
+);
+
+CREATE INDEX IF NOT EXISTS idx_system_alerts_created_at
+    ON system_alerts (created_at DESC);
+
+CREATE INDEX IF NOT EXISTS idx_system_alerts_open
+    ON system_alerts (created_at DESC)
+    WHERE acknowledged_at IS NULL;
+
+GRANT SELECT, INSERT, UPDATE, DELETE ON system_alerts TO mpms_user;
+
+COMMENT ON TABLE system_alerts IS 'Internal operational alerts raised by background tasks';
+COMMENT ON COLUMN system_alerts.alert_type IS 'Machine-readable alert kind (e.g. STUCK_NOTIFICATION)';
+COMMENT ON COLUMN system_alerts.source IS 'Background task that raised the alert';
+COMMENT ON COLUMN system_alerts.acknowledged_at IS 'When an administrator acknowledged the alert';
+
+-- ============================================================================
+-- Allow GENERATED -> FAILED for documents whose file has gone missing
+-- ============================================================================
+
+CREATE OR REPLACE FUNCTION validate_document_status_transition()
+RETURNS TRIGGER AS $$
+BEGIN
+    -- GENERATING can go to: GENERATED, FAILED
+    IF OLD.status = 'GENERATING' AND NEW.status NOT IN ('GENERATING', 'GENERATED', 'FAILED') THEN
+        RAISE EXCEPTION 'Invalid status transition from GENERATING to %', NEW.status;
+    END IF;
+
+    -- GENERATED can go to: DELIVERED, DELETED, FAILED (file missing)
+    IF OLD.status = 'GENERATED' AND NEW.status NOT IN ('GENERATED', 'DELIVERED', 'DELETED', 'FAILED') THEN
+        RAISE EXCEPTION 'Invalid status transition from GENERATED to %', NEW.status;
+    END IF;
+
+    -- DELIVERED can go to: DELETED
+    IF OLD.status = 'DELIVERED' AND NEW.status NOT IN ('DELIVERED', 'DELETED') THEN
+        RAISE EXCEPTION 'Invalid status transition from DELIVERED to %', NEW.status;
+    END IF;
+
+    -- FAILED and DELETED are final states
+    IF OLD.status IN ('FAILED', 'DELETED') AND NEW.status != OLD.status THEN
+        RAISE EXCEPTION 'Cannot change status from %', OLD.status;
+    END IF;
+
+    -- Auto-set delivered_at when moving to DELIVERED
+    IF NEW.status = 'DELIVERED' AND OLD.status != 'DELIVERED' AND NEW.delivered_at IS NULL THEN
+        NEW.delivered_at := NOW();
+    END IF;
+
+    -- Auto-set deleted_at when moving to DELETED
+    IF NEW.status = 'DELETED' AND OLD.status != 'DELETED' AND NEW.deleted_at IS NULL THEN
+        NEW.deleted_at := NOW();
+    END IF;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+-- ============================================================================
+-- Janitor settings
+-- ============================================================================
+
+INSERT INTO system_settings (
+    setting_key, setting_group, setting_name, setting_value,
+    value_type, description, default_value, is_public, is_encrypted, is_readonly
+) VALUES
+(
+    'janitor_enabled',
+    'system',
+    'Stuck-State Janitor Enabled',
+    'true',
+    'BOOLEAN',
+    'Enable or disable the background task that fails notifications and documents stuck in transient states.',
+    'true',
+    false,
+    false,
+    false
+),
+(
+    'janitor_interval_minutes',
+    'system',
+    'Janitor Interval (minutes)',
+    '10',
+    'INTEGER',
+    'How often the janitor scans for stuck notifications and documents.',
+    '10',
+    false,
+    false,
+    false
+),
+(
+    'janitor_notification_stuck_minutes',
+    'system',
+    'Stuck Notification Threshold (minutes)',
+    '30',
+    'INTEGER',
+    'Minutes a notification may stay in PROCESSING without a live worker lease before it is marked FAILED.',
+    '30',
+    false,
+    false,
+    false
+),
+(
+    'janitor_document_stuck_minutes',
+    'system',
+    'Stuck Document Threshold (minutes)',
+    '15',
+    'INTEGER',
+    'Minutes a document may stay in GENERATING before it is marked FAILED.',
+    '15',
+    false,
+    false,
+    false
+)
+ON CONFLICT (setting_key) DO NOTHING;
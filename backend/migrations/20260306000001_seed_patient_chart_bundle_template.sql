@@ -0,0 +1,208 @@
+-- ==============================================================================
+-- Seed Patient Chart Print Bundle Template
+--
+-- Template used by POST /api/v1/patients/{id}/print-bundle to assemble
+-- selected chart sections (demographics, active problems, recent visits,
+-- current medications, latest labs) into a single PDF for referrals or
+-- hospital admissions.
+--
+-- Sections are rendered in the order requested (bundle.sections); each
+-- section after the first starts on a new page (<div class="page-break">).
+--
+-- Uses ON CONFLICT (template_key) DO NOTHING for idempotent re-runs.
+-- ==============================================================================
+
+-- Temporarily disable RLS for data seeding
+ALTER TABLE document_templates DISABLE ROW LEVEL SECURITY;
+
+INSERT INTO document_templates (
+    template_key, template_name, description, document_type,
+    template_html, template_variables, header_html, footer_html, css_styles,
+    page_size, page_orientation, margin_top_mm, margin_bottom_mm, margin_left_mm, margin_right_mm,
+    is_active, is_default, language
+) VALUES (
+    'patient_chart_bundle_it',
+    'Estratto Cartella Clinica',
+    'Estratto della cartella clinica con le sezioni selezionate, per invii specialistici o ricoveri',
+    'CUSTOM',
+    E'<div class="chart-bundle">
+    <h1 class="title">ESTRATTO CARTELLA CLINICA</h1>
+
+    <table class="info-table">
+        <tr>
+            <td><strong>Paziente:</strong></td>
+            <td>{{patient.full_name}}</td>
+            <td><strong>Data di nascita:</strong></td>
+            <td>{{patient.date_of_birth}}</td>
+        </tr>
+        <tr>
+            <td><strong>Codice Fiscale:</strong></td>
+            <td>{{patient.fiscal_code}}</td>
+            <td><strong>N. cartella:</strong></td>
+            <td>{{bundle.demographics.medical_record_number}}</td>
+        </tr>
+    </table>
+
+    {% if bundle.purpose %}
+    <p class="purpose"><strong>Motivo:</strong> {{bundle.purpose}}</p>
+    {% endif %}
+
+    {% for section in bundle.sections %}
+    {% if not loop.first %}<div class="page-break"></div>{% endif %}
+
+    {% if section == "DEMOGRAPHICS" %}
+    <h2>Dati Anagrafici</h2>
+    <table class="info-table">
+        <tr><td><strong>Sesso:</strong></td><td>{{patient.gender}}</td></tr>
+        {% if bundle.demographics.address %}
+        <tr><td><strong>Indirizzo:</strong></td><td>{{bundle.demographics.address.street}}, {{bundle.demographics.address.zip}} {{bundle.demographics.address.city}} ({{bundle.demographics.address.state}})</td></tr>
+        {% endif %}
+        <tr><td><strong>Telefono:</strong></td><td>{{patient.phone}}</td></tr>
+        <tr><td><strong>Email:</strong></td><td>{{patient.email}}</td></tr>
+        {% if bundle.demographics.blood_type %}
+        <tr><td><strong>Gruppo sanguigno:</strong></td><td>{{bundle.demographics.blood_type}}</td></tr>
+        {% endif %}
+        {% if bundle.demographics.emergency_contact %}
+        <tr><td><strong>Contatto di emergenza:</strong></td><td>{{bundle.demographics.emergency_contact.name}} ({{bundle.demographics.emergency_contact.relationship}}) - {{bundle.demographics.emergency_contact.phone}}</td></tr>
+        {% endif %}
+    </table>
+    <h3>Allergie</h3>
+    {% if bundle.demographics.allergies %}
+    <ul>{% for allergy in bundle.demographics.allergies %}<li>{{allergy}}</li>{% endfor %}</ul>
+    {% else %}
+    <p>Nessuna allergia nota</p>
+    {% endif %}
+    {% if bundle.demographics.chronic_conditions %}
+    <h3>Patologie Croniche</h3>
+    <ul>{% for condition in bundle.demographics.chronic_conditions %}<li>{{condition}}</li>{% endfor %}</ul>
+    {% endif %}
+    {% endif %}
+
+    {% if section == "ACTIVE_PROBLEMS" %}
+    <h2>Problemi Attivi</h2>
+    {% if bundle.problems %}
+    <table class="data-table">
+        <thead><tr><th>Codice ICD-10</th><th>Descrizione</th><th>Tipo</th><th>Dal</th></tr></thead>
+        <tbody>
+        {% for problem in bundle.problems %}
+            <tr>
+                <td>{{problem.code}}</td>
+                <td>{{problem.description}}</td>
+                <td>{{problem.type}}</td>
+                <td>{{problem.since}}</td>
+            </tr>
+        {% endfor %}
+        </tbody>
+    </table>
+    {% else %}
+    <p>Nessun problema attivo</p>
+    {% endif %}
+    {% endif %}
+
+    {% if section == "RECENT_VISITS" %}
+    <h2>Visite Recenti</h2>
+    {% for visit in bundle.visits %}
+    <div class="visit">
+        <h3>{{visit.date}} - {{visit.type}} ({{visit.provider}})</h3>
+        {% if visit.chief_complaint %}<p><strong>Motivo:</strong> {{visit.chief_complaint}}</p>{% endif %}
+        {% if visit.vitals %}
+        <p><strong>Parametri:</strong>
+        {% if visit.vitals.blood_pressure_systolic %}PA {{visit.vitals.blood_pressure_systolic}}/{{visit.vitals.blood_pressure_diastolic}} mmHg; {% endif %}
+        {% if visit.vitals.heart_rate %}FC {{visit.vitals.heart_rate}} bpm; {% endif %}
+        {% if visit.vitals.temperature_celsius %}T {{visit.vitals.temperature_celsius}} °C; {% endif %}
+        {% if visit.vitals.oxygen_saturation %}SpO2 {{visit.vitals.oxygen_saturation}}%; {% endif %}
+        {% if visit.vitals.weight_kg %}Peso {{visit.vitals.weight_kg}} kg{% endif %}
+        </p>
+        {% endif %}
+        {% if visit.assessment %}<p><strong>Valutazione:</strong> {{visit.assessment}}</p>{% endif %}
+        {% if visit.plan %}<p><strong>Piano:</strong> {{visit.plan}}</p>{% endif %}
+    </div>
+    {% else %}
+    <p>Nessuna visita registrata</p>
+    {% endfor %}
+    {% endif %}
+
+    {% if section == "CURRENT_MEDICATIONS" %}
+    <h2>Terapia in Corso</h2>
+    {% if bundle.medications %}
+    <table class="data-table">
+        <thead><tr><th>Farmaco</th><th>Dosaggio</th><th>Frequenza</th><th>Dal</th></tr></thead>
+        <tbody>
+        {% for rx in bundle.medications %}
+            <tr>
+                <td>{{rx.name}}{% if rx.generic_name %} ({{rx.generic_name}}){% endif %}</td>
+                <td>{{rx.dosage}}</td>
+                <td>{{rx.frequency}}</td>
+                <td>{{rx.start_date}}</td>
+            </tr>
+        {% endfor %}
+        </tbody>
+    </table>
+    {% else %}
+    <p>Nessuna terapia in corso</p>
+    {% endif %}
+    {% endif %}
+
+    {% if section == "LATEST_LABS" %}
+    <h2>Ultimi Esami di Laboratorio</h2>
+    {% if bundle.labs %}
+    <table class="data-table">
+        <thead><tr><th>Data</th><th>Esame</th><th>Risultato</th><th>Riferimento</th></tr></thead>
+        <tbody>
+        {% for lab in bundle.labs %}
+            <tr>
+                <td>{{lab.date}}</td>
+                <td>{{lab.name}}</td>
+                <td>{{lab.value}} {{lab.unit}}</td>
+                <td>{{lab.reference_range}}</td>
+            </tr>
+        {% endfor %}
+        </tbody>
+    </table>
+    {% else %}
+    <p>Nessun esame di laboratorio registrato</p>
+    {% endif %}
+    {% endif %}
+    {% endfor %}
+
+    <div class="footer-section">
+        <div class="date-location">
+            <p>{{clinic.city}}, {{document.date}}</p>
+        </div>
+        <div class="signature">
+            <p class="signature-line">_________________________</p>
+            <p>Dr. {{provider.full_name}}</p>
+        </div>
+    </div>
+</div>',
+    '{"required": ["patient", "provider", "clinic", "bundle", "document"], "patient": ["full_name", "date_of_birth", "fiscal_code", "gender", "phone", "email"], "provider": ["full_name"], "clinic": ["city"], "bundle": ["sections", "purpose", "demographics", "problems", "visits", "medications", "labs"], "document": ["date"]}',
+    E'<div class="header">
+    <div class="clinic-info">
+        <h2>{{clinic.name}}</h2>
+        <p>{{clinic.address}} - {{clinic.city}} ({{clinic.province}})</p>
+        <p>Tel: {{clinic.phone}} | Email: {{clinic.email}}</p>
+    </div>
+</div>',
+    E'<div class="footer">
+    <p>Documento riservato - contiene dati sanitari personali</p>
+</div>',
+    E'.chart-bundle { font-family: Arial, sans-serif; font-size: 11pt; }
+.title { text-align: center; margin-bottom: 20px; }
+.purpose { margin: 10px 0; padding: 8px; border-left: 4px solid #333; }
+.info-table { width: 100%; margin-bottom: 15px; }
+.data-table { width: 100%; border-collapse: collapse; margin-bottom: 15px; }
+.data-table th, .data-table td { border: 1px solid #ccc; padding: 4px; text-align: left; }
+.visit { margin-bottom: 15px; }
+.page-break { page-break-before: always; }
+.footer-section { display: flex; justify-content: space-between; margin-top: 30px; }
+.signature { text-align: center; }
+.signature-line { margin: 20px 0 5px 0; }
+.header { border-bottom: 2px solid #333; padding-bottom: 10px; margin-bottom: 15px; }
+.clinic-info { text-align: center; }
+.footer { text-align: center; border-top: 1px solid #ccc; padding-top: 10px; font-size: 9pt; }',
+    'A4', 'PORTRAIT', 20, 15, 20, 20,
+    true, false, 'it'
+) ON CONFLICT (template_key) DO NOTHING;
+
+-- Re-enable RLS
+ALTER TABLE document_templates ENABLE ROW LEVEL SECURITY;
@@ -0,0 +1,85 @@
+-- Migration: Opt-in Anonymous Telemetry
+-- Date: 2026-03-07
+--
+-- An opt-in background task periodically posts anonymous operational metrics
+-- (application version, enabled features, aggregate usage counts and error
+-- rates) to a configurable endpoint. No patient data, user identifiers or
+-- free text is ever included.
+--
+-- Telemetry is disabled by default and only runs once an administrator has
+-- enabled it and configured an endpoint. Every report that is sent is stored
+-- in telemetry_reports so administrators can review exactly what left the
+-- installation; GET /api/v1/system/telemetry/preview shows the next payload
+-- before anything is sent.
+
+-- ============================================================================
+-- Sent reports
+-- ============================================================================
+
+CREATE TABLE IF NOT EXISTS telemetry_reports (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    period_start TIMESTAMPTZ NOT NULL,
+    period_end TIMESTAMPTZ NOT NULL,
+    endpoint TEXT NOT NULL,
+    payload JSONB NOT NULL,
+    status_code INTEGER,
+    error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT telemetry_reports_period_check CHECK (period_end > period_start)
+);
+
+CREATE INDEX IF NOT EXISTS idx_telemetry_reports_created_at
+    ON telemetry_reports (created_at DESC);
+
+GRANT SELECT, INSERT, UPDATE, DELETE ON telemetry_reports TO mpms_user;
+
+COMMENT ON TABLE telemetry_reports IS 'Anonymous telemetry reports posted to the configured endpoint (opt-in)';
+COMMENT ON COLUMN telemetry_reports.payload IS 'Exact JSON body that was sent';
+COMMENT ON COLUMN telemetry_reports.status_code IS 'HTTP status returned by the endpoint (NULL if the request failed)';
+
+-- ============================================================================
+-- Telemetry settings
+-- ============================================================================
+
+INSERT INTO system_settings (
+    setting_key, setting_group, setting_name, setting_value,
+    value_type, description, default_value, is_public, is_encrypted, is_readonly
+) VALUES
+(
+    'telemetry_enabled',
+    'system',
+    'Anonymous Telemetry Enabled',
+    'false',
+    'BOOLEAN',
+    'Opt in to sending anonymous usage statistics (version, feature usage counts, error rates). No patient or user data is sent.',
+    'false',
+    false,
+    false,
+    false
+),
+(
+    'telemetry_endpoint',
+    'system',
+    'Telemetry Endpoint',
+    '""',
+    'STRING',
+    'HTTPS URL that anonymous telemetry reports are posted to. Telemetry is not sent while empty.',
+    '""',
+    false,
+    false,
+    false
+),
+(
+    'telemetry_interval_hours',
+    'system',
+    'Telemetry Interval (hours)',
+    '24',
+    'INTEGER',
+    'How often anonymous telemetry reports are sent.',
+    '24',
+    false,
+    false,
+    false
+)
+ON CONFLICT (setting_key) DO NOTHING;
@@ -0,0 +1,26 @@
+-- Migration: Revoked Token Denylist
+-- Date: 2026-03-08
+--
+-- Server-side denylist of revoked JWT IDs (jti). POST /api/v1/auth/revoke adds
+-- a row; the authentication middleware keeps an in-memory copy and rejects any
+-- token whose jti is listed. Rows are only needed until the token would have
+-- expired on its own, so expired rows are pruned at startup.
+
+CREATE TABLE IF NOT EXISTS revoked_tokens (
+    jti TEXT PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    token_type VARCHAR(20) NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    revoked_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    revoked_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    reason TEXT,
+
+    CONSTRAINT revoked_tokens_type_check CHECK (token_type IN ('access', 'refresh'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_revoked_tokens_expires_at ON revoked_tokens(expires_at);
+CREATE INDEX IF NOT EXISTS idx_revoked_tokens_user_id ON revoked_tokens(user_id);
+
+COMMENT ON TABLE revoked_tokens IS 'Revoked JWT IDs checked by the authentication middleware until the token expires';
+
+GRANT SELECT, INSERT, DELETE ON revoked_tokens TO mpms_user;
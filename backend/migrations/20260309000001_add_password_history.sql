@@ -0,0 +1,56 @@
+-- Migration: Password Expiry and History Policy
+-- Date: 2026-03-09
+--
+-- Passwords older than security.password_expiry_days must be changed at the
+-- next login (the login response carries requiresPasswordChange), and a new
+-- password may not match the current one or any of the last
+-- security.password_history_count passwords.
+--
+-- users.password_changed_at existed but was never maintained, so it is reset
+-- for existing accounts to start every user with a full expiry period instead
+-- of forcing everyone to change their password on upgrade.
+
+-- ============================================================================
+-- Password history (Argon2 hashes only)
+-- ============================================================================
+
+CREATE TABLE IF NOT EXISTS password_history (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    password_hash VARCHAR(255) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_password_history_user_created
+    ON password_history (user_id, created_at DESC);
+
+COMMENT ON TABLE password_history IS 'Previous password hashes per user, used to prevent password reuse';
+
+GRANT SELECT, INSERT, DELETE ON password_history TO mpms_user;
+
+-- ============================================================================
+-- Start the expiry clock for existing accounts
+-- ============================================================================
+
+UPDATE users SET password_changed_at = NOW();
+
+-- ============================================================================
+-- Settings
+-- ============================================================================
+
+INSERT INTO system_settings (
+    setting_key, setting_group, setting_name, setting_value,
+    value_type, description, default_value, is_public, is_encrypted, is_readonly
+) VALUES (
+    'security.password_history_count',
+    'security',
+    'Password History',
+    '5',
+    'INTEGER',
+    'Number of previous passwords that cannot be reused (the current password is always rejected)',
+    '5',
+    false,
+    false,
+    false
+)
+ON CONFLICT (setting_key) DO NOTHING;
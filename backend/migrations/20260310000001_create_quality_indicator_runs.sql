@@ -0,0 +1,26 @@
+-- Migration: GP Quality Indicator Runs
+-- Date: 2026-03-10
+--
+-- Audit trail of every computation of the regional GP quality indicators:
+-- the measurement period, algorithm version, criteria and population counts
+-- used (no patient identifiers), and the resulting indicators.
+
+CREATE TABLE IF NOT EXISTS quality_indicator_runs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    period_start DATE NOT NULL,
+    period_end DATE NOT NULL,
+    algorithm_version VARCHAR(20) NOT NULL,
+    inputs JSONB NOT NULL,
+    results JSONB NOT NULL,
+    generated_by UUID NOT NULL REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT quality_indicator_runs_period_check CHECK (period_end >= period_start)
+);
+
+CREATE INDEX IF NOT EXISTS idx_quality_indicator_runs_created
+    ON quality_indicator_runs (created_at DESC);
+
+COMMENT ON TABLE quality_indicator_runs IS 'Audit trail of GP quality indicator computations (aggregate data only)';
+
+GRANT SELECT, INSERT ON quality_indicator_runs TO mpms_user;
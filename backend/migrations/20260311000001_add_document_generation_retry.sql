@@ -0,0 +1,67 @@
+-- Migration: Document Generation Retry
+-- Date: 2026-03-11
+--
+-- Failed document generations (missing font, broken template) can be re-run
+-- with POST /api/v1/documents/:id/retry once the underlying issue is fixed.
+-- The original request is stored (encrypted, it may contain PHI) so the retry
+-- reproduces it, and the same document row is reused so its id and audit
+-- trail are preserved.
+
+ALTER TABLE generated_documents
+    ADD COLUMN IF NOT EXISTS generation_request JSONB,
+    ADD COLUMN IF NOT EXISTS retry_count INT NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS last_retried_at TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS last_retried_by UUID REFERENCES users(id);
+
+COMMENT ON COLUMN generated_documents.generation_request IS 'Encrypted original generation request, used to retry failed generations';
+COMMENT ON COLUMN generated_documents.retry_count IS 'Number of times generation was retried';
+
+CREATE INDEX IF NOT EXISTS idx_generated_documents_failed
+    ON generated_documents (created_at DESC)
+    WHERE status = 'FAILED';
+
+-- ============================================================================
+-- Allow FAILED -> GENERATING (retry)
+-- ============================================================================
+
+CREATE OR REPLACE FUNCTION validate_document_status_transition()
+RETURNS TRIGGER AS $$
+BEGIN
+    -- GENERATING can go to: GENERATED, FAILED
+    IF OLD.status = 'GENERATING' AND NEW.status NOT IN ('GENERATING', 'GENERATED', 'FAILED') THEN
+        RAISE EXCEPTION 'Invalid status transition from GENERATING to %', NEW.status;
+    END IF;
+
+    -- GENERATED can go to: DELIVERED, DELETED, FAILED (file missing)
+    IF OLD.status = 'GENERATED' AND NEW.status NOT IN ('GENERATED', 'DELIVERED', 'DELETED', 'FAILED') THEN
+        RAISE EXCEPTION 'Invalid status transition from GENERATED to %', NEW.status;
+    END IF;
+
+    -- DELIVERED can go to: DELETED
+    IF OLD.status = 'DELIVERED' AND NEW.status NOT IN ('DELIVERED', 'DELETED') THEN
+        RAISE EXCEPTION 'Invalid status transition from DELIVERED to %', NEW.status;
+    END IF;
+
+    -- FAILED can go to: GENERATING (retry)
+    IF OLD.status = 'FAILED' AND NEW.status NOT IN ('FAILED', 'GENERATING') THEN
+        RAISE EXCEPTION 'Invalid status transition from FAILED to %', NEW.status;
+    END IF;
+
+    -- DELETED is a final state
+    IF OLD.status = 'DELETED' AND NEW.status != OLD.status THEN
+        RAISE EXCEPTION 'Cannot change status from %', OLD.status;
+    END IF;
+
+    -- Auto-set delivered_at when moving to DELIVERED
+    IF NEW.status = 'DELIVERED' AND OLD.status != 'DELIVERED' AND NEW.delivered_at IS NULL THEN
+        NEW.delivered_at := NOW();
+    END IF;
+
+    -- Auto-set deleted_at when moving to DELETED
+    IF NEW.status = 'DELETED' AND OLD.status != 'DELETED' AND NEW.deleted_at IS NULL THEN
+        NEW.deleted_at := NOW();
+    END IF;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
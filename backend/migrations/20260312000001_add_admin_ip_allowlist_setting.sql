@@ -0,0 +1,23 @@
+-- Migration: Admin IP Allowlist Setting
+-- Date: 2026-03-12
+--
+-- CIDR ranges allowed to reach the user management, settings and audit log
+-- endpoints. An empty list falls back to ADMIN_IP_ALLOWLIST from the
+-- environment; when both are empty the admin routes are not IP-restricted.
+
+INSERT INTO system_settings (
+    setting_key, setting_group, setting_name, setting_value,
+    value_type, description, default_value, is_public, is_encrypted, is_readonly
+) VALUES (
+    'security.admin_ip_allowlist',
+    'security',
+    'Admin IP Allowlist',
+    '[]',
+    'ARRAY',
+    'CIDR ranges or addresses allowed to access user management, settings and audit logs (empty = use server configuration)',
+    '[]',
+    false,
+    false,
+    false
+)
+ON CONFLICT (setting_key) DO NOTHING;
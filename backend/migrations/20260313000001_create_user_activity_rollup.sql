@@ -0,0 +1,37 @@
+-- Migration: User Activity Rollup
+-- Date: 2026-03-13
+--
+-- Daily per-user activity aggregated from audit_logs for the access review
+-- report (GET /api/v1/audit-logs/activity-report). Completed days are rolled
+-- up the first time a report covers them; audit logs are immutable, so the
+-- rollup never needs refreshing. user_activity_rollup_days records which days
+-- have been aggregated, including days without activity.
+
+CREATE TABLE IF NOT EXISTS user_activity_daily (
+    activity_date DATE NOT NULL,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    logins INTEGER NOT NULL DEFAULT 0,
+    patient_ids UUID[] NOT NULL DEFAULT '{}',
+    patient_access_events INTEGER NOT NULL DEFAULT 0,
+    documents_generated INTEGER NOT NULL DEFAULT 0,
+    after_hours_events INTEGER NOT NULL DEFAULT 0,
+    total_events INTEGER NOT NULL DEFAULT 0,
+    aggregated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (activity_date, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_user_activity_daily_user
+    ON user_activity_daily (user_id, activity_date);
+
+CREATE TABLE IF NOT EXISTS user_activity_rollup_days (
+    activity_date DATE PRIMARY KEY,
+    aggregated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+COMMENT ON TABLE user_activity_daily IS 'Per-user daily activity derived from audit_logs (Europe/Rome days)';
+COMMENT ON COLUMN user_activity_daily.patient_ids IS 'Distinct patients accessed that day, used to count distinct patients over a period';
+COMMENT ON COLUMN user_activity_daily.after_hours_events IS 'Requests outside the working hours configured for that date';
+COMMENT ON TABLE user_activity_rollup_days IS 'Days already aggregated into user_activity_daily';
+
+GRANT SELECT, INSERT, UPDATE ON user_activity_daily TO mpms_user;
+GRANT SELECT, INSERT ON user_activity_rollup_days TO mpms_user;
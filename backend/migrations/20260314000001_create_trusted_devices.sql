@@ -0,0 +1,27 @@
+-- Migration: Trusted Devices
+-- Date: 2026-03-14
+--
+-- Devices on which a user chose "remember this device" after completing MFA.
+-- Logins from a trusted device skip the MFA code step until expires_at
+-- (30 days). The device cookie is a signed token carrying the row ID; the
+-- row is checked on every use so revocation takes effect immediately.
+
+CREATE TABLE IF NOT EXISTS trusted_devices (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    device_name VARCHAR(255),
+    ip_address INET,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_used_at TIMESTAMPTZ,
+    expires_at TIMESTAMPTZ NOT NULL,
+    revoked_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_trusted_devices_user_active
+    ON trusted_devices (user_id, expires_at)
+    WHERE revoked_at IS NULL;
+
+COMMENT ON TABLE trusted_devices IS 'Devices allowed to skip the MFA code step at login';
+COMMENT ON COLUMN trusted_devices.device_name IS 'User-Agent of the device when it was trusted';
+
+GRANT SELECT, INSERT, UPDATE, DELETE ON trusted_devices TO mpms_user;
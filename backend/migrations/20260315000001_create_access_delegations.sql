@@ -0,0 +1,215 @@
+-- Migration: Access Delegations for Locum Doctors
+-- Date: 2026-03-15
+--
+-- A delegation grants a delegate (typically a locum) temporary access to a
+-- subset of the grantor's patients and appointments. Access is limited to the
+-- [starts_at, ends_at) window and stops as soon as the window closes or the
+-- delegation is revoked - there is no job to run, the RLS helpers below
+-- compare against NOW() on every query.
+--
+-- Locum accounts (users.is_locum) only see patients covered by an active
+-- delegation. Regular doctors keep shared patient access and additionally
+-- gain access to the grantor's delegated appointments.
+
+-- ====================
+-- LOCUM FLAG
+-- ====================
+ALTER TABLE users ADD COLUMN IF NOT EXISTS is_locum BOOLEAN NOT NULL DEFAULT FALSE;
+
+COMMENT ON COLUMN users.is_locum IS 'Locum account: patient access only through active access delegations';
+
+-- ====================
+-- DELEGATIONS
+-- ====================
+CREATE TABLE IF NOT EXISTS access_delegations (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    grantor_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    delegate_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    reason TEXT,
+    starts_at TIMESTAMPTZ NOT NULL,
+    ends_at TIMESTAMPTZ NOT NULL,
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    revoked_at TIMESTAMPTZ,
+    revoked_by UUID REFERENCES users(id) ON DELETE SET NULL,
+
+    CONSTRAINT access_delegations_window CHECK (ends_at > starts_at),
+    CONSTRAINT access_delegations_distinct_users CHECK (grantor_id <> delegate_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_access_delegations_delegate_window
+    ON access_delegations (delegate_id, starts_at, ends_at)
+    WHERE revoked_at IS NULL;
+
+CREATE INDEX IF NOT EXISTS idx_access_delegations_grantor
+    ON access_delegations (grantor_id, starts_at DESC);
+
+-- Each scope row covers either one patient (all of the grantor's appointments
+-- for that patient) or one appointment.
+CREATE TABLE IF NOT EXISTS access_delegation_scopes (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    delegation_id UUID NOT NULL REFERENCES access_delegations(id) ON DELETE CASCADE,
+    patient_id UUID REFERENCES patients(id) ON DELETE CASCADE,
+    appointment_id UUID REFERENCES appointments(id) ON DELETE CASCADE,
+
+    CONSTRAINT access_delegation_scopes_one_target
+        CHECK (num_nonnulls(patient_id, appointment_id) = 1)
+);
+
+CREATE INDEX IF NOT EXISTS idx_access_delegation_scopes_delegation
+    ON access_delegation_scopes (delegation_id);
+CREATE INDEX IF NOT EXISTS idx_access_delegation_scopes_patient
+    ON access_delegation_scopes (patient_id) WHERE patient_id IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_access_delegation_scopes_appointment
+    ON access_delegation_scopes (appointment_id) WHERE appointment_id IS NOT NULL;
+
+COMMENT ON TABLE access_delegations IS 'Time-limited access granted by a doctor to a delegate (locum)';
+COMMENT ON TABLE access_delegation_scopes IS 'Patients and appointments covered by an access delegation';
+
+GRANT SELECT, INSERT, UPDATE, DELETE ON access_delegations TO mpms_user;
+GRANT SELECT, INSERT, UPDATE, DELETE ON access_delegation_scopes TO mpms_user;
+
+-- ====================
+-- HELPER FUNCTIONS FOR RLS
+-- ====================
+
+-- Whether the current user is a locum account
+CREATE OR REPLACE FUNCTION is_locum()
+RETURNS BOOLEAN AS $$
+BEGIN
+    RETURN COALESCE(
+        (SELECT is_locum FROM users WHERE id = get_current_user_id()),
+        FALSE
+    );
+EXCEPTION
+    WHEN OTHERS THEN
+        RETURN FALSE;
+END;
+$$ LANGUAGE plpgsql STABLE SECURITY DEFINER;
+
+-- Whether an active delegation to the current user covers the patient
+-- (directly, or through one of the patient's delegated appointments)
+CREATE OR REPLACE FUNCTION has_delegated_patient_access(p_patient_id UUID)
+RETURNS BOOLEAN AS $$
+BEGIN
+    RETURN EXISTS (
+        SELECT 1
+        FROM access_delegations d
+        JOIN access_delegation_scopes s ON s.delegation_id = d.id
+        LEFT JOIN appointments a ON a.id = s.appointment_id
+        WHERE d.delegate_id = get_current_user_id()
+          AND d.revoked_at IS NULL
+          AND NOW() >= d.starts_at
+          AND NOW() < d.ends_at
+          AND (s.patient_id = p_patient_id OR a.patient_id = p_patient_id)
+    );
+EXCEPTION
+    WHEN OTHERS THEN
+        RETURN FALSE;
+END;
+$$ LANGUAGE plpgsql STABLE SECURITY DEFINER;
+
+-- Whether an active delegation from the appointment's provider to the current
+-- user covers the appointment (by patient or by appointment ID)
+CREATE OR REPLACE FUNCTION has_delegated_appointment_access(
+    p_provider_id UUID,
+    p_patient_id UUID,
+    p_appointment_id UUID
+)
+RETURNS BOOLEAN AS $$
+BEGIN
+    RETURN EXISTS (
+        SELECT 1
+        FROM access_delegations d
+        JOIN access_delegation_scopes s ON s.delegation_id = d.id
+        WHERE d.delegate_id = get_current_user_id()
+          AND d.grantor_id = p_provider_id
+          AND d.revoked_at IS NULL
+          AND NOW() >= d.starts_at
+          AND NOW() < d.ends_at
+          AND (s.patient_id = p_patient_id OR s.appointment_id = p_appointment_id)
+    );
+EXCEPTION
+    WHEN OTHERS THEN
+        RETURN FALSE;
+END;
+$$ LANGUAGE plpgsql STABLE SECURITY DEFINER;
+
+-- ====================
+-- PATIENTS
+-- ====================
+DROP POLICY IF EXISTS patients_select_policy ON patients;
+CREATE POLICY patients_select_policy ON patients
+    FOR SELECT
+    USING (is_doctor() AND (NOT is_locum() OR has_delegated_patient_access(id)));
+
+DROP POLICY IF EXISTS patients_insert_policy ON patients;
+CREATE POLICY patients_insert_policy ON patients
+    FOR INSERT
+    WITH CHECK (is_doctor() AND NOT is_locum());
+
+DROP POLICY IF EXISTS patients_update_policy ON patients;
+CREATE POLICY patients_update_policy ON patients
+    FOR UPDATE
+    USING (is_doctor() AND (NOT is_locum() OR has_delegated_patient_access(id)))
+    WITH CHECK (is_doctor() AND (NOT is_locum() OR has_delegated_patient_access(id)));
+
+-- ====================
+-- PATIENT_INSURANCE
+-- ====================
+DROP POLICY IF EXISTS patient_insurance_select_policy ON patient_insurance;
+CREATE POLICY patient_insurance_select_policy ON patient_insurance
+    FOR SELECT
+    USING (is_doctor() AND (NOT is_locum() OR has_delegated_patient_access(patient_id)));
+
+DROP POLICY IF EXISTS patient_insurance_insert_policy ON patient_insurance;
+CREATE POLICY patient_insurance_insert_policy ON patient_insurance
+    FOR INSERT
+    WITH CHECK (is_doctor() AND (NOT is_locum() OR has_delegated_patient_access(patient_id)));
+
+DROP POLICY IF EXISTS patient_insurance_update_policy ON patient_insurance;
+CREATE POLICY patient_insurance_update_policy ON patient_insurance
+    FOR UPDATE
+    USING (is_doctor() AND (NOT is_locum() OR has_delegated_patient_access(patient_id)))
+    WITH CHECK (is_doctor() AND (NOT is_locum() OR has_delegated_patient_access(patient_id)));
+
+-- ====================
+-- APPOINTMENTS
+-- ====================
+-- Delegates may read, create and update (reschedule, cancel, complete) the
+-- grantor's delegated appointments. Deleting stays with the provider.
+DROP POLICY IF EXISTS appointments_select_policy ON appointments;
+CREATE POLICY appointments_select_policy ON appointments
+    FOR SELECT
+    USING (
+        is_admin()
+        OR (is_doctor() AND provider_id = get_current_user_id())
+        OR (is_doctor() AND has_delegated_appointment_access(provider_id, patient_id, id))
+    );
+
+DROP POLICY IF EXISTS appointments_insert_policy ON appointments;
+CREATE POLICY appointments_insert_policy ON appointments
+    FOR INSERT
+    WITH CHECK (
+        is_doctor() AND (
+            (provider_id = get_current_user_id()
+                AND (NOT is_locum() OR has_delegated_patient_access(patient_id)))
+            OR has_delegated_appointment_access(provider_id, patient_id, NULL)
+        )
+    );
+
+DROP POLICY IF EXISTS appointments_update_policy ON appointments;
+CREATE POLICY appointments_update_policy ON appointments
+    FOR UPDATE
+    USING (
+        is_doctor() AND (
+            provider_id = get_current_user_id()
+            OR has_delegated_appointment_access(provider_id, patient_id, id)
+        )
+    )
+    WITH CHECK (
+        is_doctor() AND (
+            provider_id = get_current_user_id()
+            OR has_delegated_appointment_access(provider_id, patient_id, id)
+        )
+    );
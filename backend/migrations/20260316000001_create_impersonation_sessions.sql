@@ -0,0 +1,38 @@
+-- Migration: Admin Impersonation
+-- Date: 2026-03-16
+--
+-- Admins can act as another user for support. Each session is recorded here,
+-- and every audit log row written while impersonating carries the admin in
+-- impersonator_id alongside the impersonated user in user_id.
+
+CREATE TABLE IF NOT EXISTS impersonation_sessions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    impersonator_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    target_user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    reason TEXT NOT NULL,
+    token_jti VARCHAR(64) NOT NULL UNIQUE,
+    started_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL,
+    ended_at TIMESTAMPTZ,
+    ip_address INET,
+
+    CONSTRAINT impersonation_sessions_distinct_users CHECK (impersonator_id <> target_user_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_impersonation_sessions_started
+    ON impersonation_sessions (started_at DESC);
+CREATE INDEX IF NOT EXISTS idx_impersonation_sessions_target
+    ON impersonation_sessions (target_user_id, started_at DESC);
+
+COMMENT ON TABLE impersonation_sessions IS 'Admin impersonation sessions (support access as another user)';
+
+GRANT SELECT, INSERT, UPDATE ON impersonation_sessions TO mpms_user;
+
+-- Double attribution: admin acting as audit_logs.user_id
+ALTER TABLE audit_logs ADD COLUMN IF NOT EXISTS impersonator_id UUID;
+
+CREATE INDEX IF NOT EXISTS idx_audit_logs_impersonator
+    ON audit_logs (impersonator_id, created_at DESC)
+    WHERE impersonator_id IS NOT NULL;
+
+COMMENT ON COLUMN audit_logs.impersonator_id IS 'Admin who performed the action while impersonating user_id';
@@ -0,0 +1,25 @@
+-- Migration: Casbin Policy Storage
+-- Date: 2026-03-17
+--
+-- Casbin RBAC rules are stored in the database so admins can change
+-- permissions at runtime. On first start the table is seeded from
+-- casbin/policy.csv; afterwards the table is the source of truth.
+
+CREATE TABLE IF NOT EXISTS casbin_rules (
+    id BIGSERIAL PRIMARY KEY,
+    ptype VARCHAR(12) NOT NULL,
+    v0 VARCHAR(128) NOT NULL DEFAULT '',
+    v1 VARCHAR(128) NOT NULL DEFAULT '',
+    v2 VARCHAR(128) NOT NULL DEFAULT '',
+    v3 VARCHAR(128) NOT NULL DEFAULT '',
+    v4 VARCHAR(128) NOT NULL DEFAULT '',
+    v5 VARCHAR(128) NOT NULL DEFAULT '',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT casbin_rules_unique UNIQUE (ptype, v0, v1, v2, v3, v4, v5)
+);
+
+COMMENT ON TABLE casbin_rules IS 'Casbin RBAC policy rules (p = role permissions, g = role assignments)';
+
+GRANT SELECT, INSERT, UPDATE, DELETE ON casbin_rules TO mpms_user;
+GRANT USAGE, SELECT ON SEQUENCE casbin_rules_id_seq TO mpms_user;
@@ -0,0 +1,223 @@
+-- Migration: NURSE and RECEPTIONIST Roles
+-- Date: 2026-03-18
+--
+-- Two staff roles with narrower access than DOCTOR:
+--
+-- NURSE: reads patients, appointments, visits, diagnoses and prescriptions;
+--   creates and edits DRAFT visits (vitals, intake notes) for any provider.
+--   Cannot sign, lock or delete visits, and cannot write diagnoses or
+--   prescriptions.
+-- RECEPTIONIST: patient demographics and insurance, scheduling for any
+--   provider, appointment notifications. No access to clinical tables
+--   (visits, diagnoses, prescriptions, documents).
+--
+-- is_doctor() still means ADMIN or DOCTOR, so every existing policy keeps
+-- excluding the new roles. Their access is granted by the additional
+-- permissive policies below (PostgreSQL ORs permissive policies together).
+
+-- ====================
+-- ROLE CONSTRAINTS
+-- ====================
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_role_check;
+ALTER TABLE users ADD CONSTRAINT users_role_check
+    CHECK (role IN ('ADMIN', 'DOCTOR', 'NURSE', 'RECEPTIONIST'));
+
+COMMENT ON COLUMN users.role IS 'User role: ADMIN, DOCTOR, NURSE or RECEPTIONIST';
+
+ALTER TABLE user_invitations DROP CONSTRAINT IF EXISTS user_invitations_role_check;
+ALTER TABLE user_invitations ADD CONSTRAINT user_invitations_role_check
+    CHECK (role IN ('ADMIN', 'DOCTOR', 'NURSE', 'RECEPTIONIST'));
+
+-- ====================
+-- HELPER FUNCTIONS FOR RLS
+-- ====================
+
+-- Function to check if current user is a nurse
+CREATE OR REPLACE FUNCTION is_nurse()
+RETURNS BOOLEAN AS $$
+BEGIN
+    RETURN current_setting('app.current_user_role', TRUE) = 'NURSE';
+EXCEPTION
+    WHEN OTHERS THEN
+        RETURN FALSE;
+END;
+$$ LANGUAGE plpgsql STABLE SECURITY DEFINER;
+
+-- Function to check if current user is a receptionist
+CREATE OR REPLACE FUNCTION is_receptionist()
+RETURNS BOOLEAN AS $$
+BEGIN
+    RETURN current_setting('app.current_user_role', TRUE) = 'RECEPTIONIST';
+EXCEPTION
+    WHEN OTHERS THEN
+        RETURN FALSE;
+END;
+$$ LANGUAGE plpgsql STABLE SECURITY DEFINER;
+
+-- ====================
+-- PATIENTS
+-- ====================
+DROP POLICY IF EXISTS patients_staff_select_policy ON patients;
+CREATE POLICY patients_staff_select_policy ON patients
+    FOR SELECT
+    USING (is_nurse() OR is_receptionist());
+
+DROP POLICY IF EXISTS patients_staff_insert_policy ON patients;
+CREATE POLICY patients_staff_insert_policy ON patients
+    FOR INSERT
+    WITH CHECK (is_receptionist());
+
+DROP POLICY IF EXISTS patients_staff_update_policy ON patients;
+CREATE POLICY patients_staff_update_policy ON patients
+    FOR UPDATE
+    USING (is_receptionist())
+    WITH CHECK (is_receptionist());
+
+-- ====================
+-- PATIENT_INSURANCE
+-- ====================
+DROP POLICY IF EXISTS patient_insurance_staff_select_policy ON patient_insurance;
+CREATE POLICY patient_insurance_staff_select_policy ON patient_insurance
+    FOR SELECT
+    USING (is_receptionist());
+
+DROP POLICY IF EXISTS patient_insurance_staff_insert_policy ON patient_insurance;
+CREATE POLICY patient_insurance_staff_insert_policy ON patient_insurance
+    FOR INSERT
+    WITH CHECK (is_receptionist());
+
+DROP POLICY IF EXISTS patient_insurance_staff_update_policy ON patient_insurance;
+CREATE POLICY patient_insurance_staff_update_policy ON patient_insurance
+    FOR UPDATE
+    USING (is_receptionist())
+    WITH CHECK (is_receptionist());
+
+-- ====================
+-- APPOINTMENTS
+-- ====================
+-- Front desk schedules for every provider; nurses see the practice schedule.
+-- Deleting stays with the provider and admins (receptionists cancel instead).
+DROP POLICY IF EXISTS appointments_staff_select_policy ON appointments;
+CREATE POLICY appointments_staff_select_policy ON appointments
+    FOR SELECT
+    USING (is_nurse() OR is_receptionist());
+
+DROP POLICY IF EXISTS appointments_staff_insert_policy ON appointments;
+CREATE POLICY appointments_staff_insert_policy ON appointments
+    FOR INSERT
+    WITH CHECK (is_receptionist());
+
+DROP POLICY IF EXISTS appointments_staff_update_policy ON appointments;
+CREATE POLICY appointments_staff_update_policy ON appointments
+    FOR UPDATE
+    USING (is_receptionist())
+    WITH CHECK (is_receptionist());
+
+-- ====================
+-- VISITS
+-- ====================
+-- Nurses work on drafts only: a visit they move out of DRAFT fails WITH CHECK,
+-- so signing and locking remain with the provider.
+DROP POLICY IF EXISTS visits_nurse_select_policy ON visits;
+CREATE POLICY visits_nurse_select_policy ON visits
+    FOR SELECT
+    USING (is_nurse());
+
+DROP POLICY IF EXISTS visits_nurse_insert_policy ON visits;
+CREATE POLICY visits_nurse_insert_policy ON visits
+    FOR INSERT
+    WITH CHECK (is_nurse() AND status = 'DRAFT');
+
+DROP POLICY IF EXISTS visits_nurse_update_policy ON visits;
+CREATE POLICY visits_nurse_update_policy ON visits
+    FOR UPDATE
+    USING (is_nurse() AND status = 'DRAFT')
+    WITH CHECK (is_nurse() AND status = 'DRAFT');
+
+-- ====================
+-- VISIT_DIAGNOSES / PRESCRIPTIONS (read-only for nurses)
+-- ====================
+DROP POLICY IF EXISTS visit_diagnoses_nurse_select_policy ON visit_diagnoses;
+CREATE POLICY visit_diagnoses_nurse_select_policy ON visit_diagnoses
+    FOR SELECT
+    USING (is_nurse());
+
+DROP POLICY IF EXISTS prescriptions_nurse_select_policy ON prescriptions;
+CREATE POLICY prescriptions_nurse_select_policy ON prescriptions
+    FOR SELECT
+    USING (is_nurse());
+
+-- ====================
+-- NOTIFICATIONS
+-- ====================
+-- Scheduling queues appointment confirmations and reminders
+DROP POLICY IF EXISTS notification_queue_staff_select_policy ON notification_queue;
+CREATE POLICY notification_queue_staff_select_policy ON notification_queue
+    FOR SELECT
+    USING (is_receptionist());
+
+DROP POLICY IF EXISTS notification_queue_staff_insert_policy ON notification_queue;
+CREATE POLICY notification_queue_staff_insert_policy ON notification_queue
+    FOR INSERT
+    WITH CHECK (is_receptionist());
+
+DROP POLICY IF EXISTS notification_queue_staff_update_policy ON notification_queue;
+CREATE POLICY notification_queue_staff_update_policy ON notification_queue
+    FOR UPDATE
+    USING (is_receptionist())
+    WITH CHECK (is_receptionist());
+
+DROP POLICY IF EXISTS patient_notification_prefs_staff_select_policy ON patient_notification_preferences;
+CREATE POLICY patient_notification_prefs_staff_select_policy ON patient_notification_preferences
+    FOR SELECT
+    USING (is_receptionist());
+
+DROP POLICY IF EXISTS patient_notification_prefs_staff_insert_policy ON patient_notification_preferences;
+CREATE POLICY patient_notification_prefs_staff_insert_policy ON patient_notification_preferences
+    FOR INSERT
+    WITH CHECK (is_receptionist());
+
+DROP POLICY IF EXISTS patient_notification_prefs_staff_update_policy ON patient_notification_preferences;
+CREATE POLICY patient_notification_prefs_staff_update_policy ON patient_notification_preferences
+    FOR UPDATE
+    USING (is_receptionist())
+    WITH CHECK (is_receptionist());
+
+-- ====================
+-- CASBIN POLICIES
+-- ====================
+-- Installs that already seeded casbin_rules from casbin/policy.csv get the new
+-- role permissions here. An empty table is left alone so the first start
+-- still seeds it from the policy file (which includes these rules).
+INSERT INTO casbin_rules (ptype, v0, v1, v2)
+SELECT v.ptype, v.v0, v.v1, v.v2
+FROM (VALUES
+    ('p', 'NURSE', 'patients', 'read'),
+    ('p', 'NURSE', 'appointments', 'read'),
+    ('p', 'NURSE', 'visits', 'create'),
+    ('p', 'NURSE', 'visits', 'read'),
+    ('p', 'NURSE', 'visits', 'update'),
+    ('p', 'NURSE', 'diagnoses', 'read'),
+    ('p', 'NURSE', 'prescriptions', 'read'),
+    ('p', 'NURSE', 'drug_interactions', 'read'),
+    ('p', 'NURSE', 'visit_templates', 'read'),
+    ('p', 'NURSE', 'users', 'read_own'),
+    ('p', 'NURSE', 'users', 'update_own'),
+    ('p', 'NURSE', 'mfa', 'manage_own'),
+    ('p', 'NURSE', 'settings', 'read'),
+    ('p', 'RECEPTIONIST', 'patients', 'create'),
+    ('p', 'RECEPTIONIST', 'patients', 'read'),
+    ('p', 'RECEPTIONIST', 'patients', 'update'),
+    ('p', 'RECEPTIONIST', 'appointments', 'create'),
+    ('p', 'RECEPTIONIST', 'appointments', 'read'),
+    ('p', 'RECEPTIONIST', 'appointments', 'update'),
+    ('p', 'RECEPTIONIST', 'notifications', 'create'),
+    ('p', 'RECEPTIONIST', 'notifications', 'read'),
+    ('p', 'RECEPTIONIST', 'notifications', 'update'),
+    ('p', 'RECEPTIONIST', 'users', 'read_own'),
+    ('p', 'RECEPTIONIST', 'users', 'update_own'),
+    ('p', 'RECEPTIONIST', 'mfa', 'manage_own'),
+    ('p', 'RECEPTIONIST', 'settings', 'read')
+) AS v (ptype, v0, v1, v2)
+WHERE EXISTS (SELECT 1 FROM casbin_rules)
+ON CONFLICT (ptype, v0, v1, v2, v3, v4, v5) DO NOTHING;
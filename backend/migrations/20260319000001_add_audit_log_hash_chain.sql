@@ -0,0 +1,150 @@
+-- Migration: Audit Log Hash Chain
+-- Date: 2026-03-19
+--
+-- Makes the audit trail tamper-evident. Every new audit_logs row gets a
+-- sequence number and a SHA-256 hash over the previous row's hash and its
+-- own contents, so modifying, deleting or reordering any row (e.g. by a
+-- superuser bypassing the immutability triggers) breaks the chain from that
+-- point on. GET /api/v1/audit-logs/verify and a background job recompute the
+-- chain to detect this.
+--
+-- The last sequence number and hash are kept in audit_log_chain_head, which
+-- is locked for the duration of each insert. Inserts are therefore
+-- serialized, and rows deleted from the end of the chain show up as a
+-- mismatch against the head.
+--
+-- Canonical row encoding (hashed as UTF-8):
+--   prev_hash, chain_seq, user_id, action, entity_type, entity_id,
+--   changes::text, ip_address::text, user_agent, request_id,
+--   impersonator_id, created_at (UTC, microseconds, ISO 8601)
+-- each written as "<octet length>:<value>", or "-" for NULL, joined by "|".
+-- Length prefixes keep a value containing "|" from shifting into its
+-- neighbour. The encoding is mirrored by services/audit_chain_service.rs.
+--
+-- Rows written before this migration keep chain_seq NULL: the immutability
+-- triggers forbid backfilling them, and they are reported as unchained.
+
+-- ====================
+-- CHAIN COLUMNS
+-- ====================
+
+ALTER TABLE audit_logs
+    ADD COLUMN IF NOT EXISTS chain_seq BIGINT,
+    ADD COLUMN IF NOT EXISTS prev_hash TEXT,
+    ADD COLUMN IF NOT EXISTS row_hash TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_audit_logs_chain_seq
+    ON audit_logs(chain_seq)
+    WHERE chain_seq IS NOT NULL;
+
+COMMENT ON COLUMN audit_logs.chain_seq IS 'Position in the hash chain (NULL for rows written before chaining)';
+COMMENT ON COLUMN audit_logs.prev_hash IS 'row_hash of the previous row in the chain (64 zeros for the first)';
+COMMENT ON COLUMN audit_logs.row_hash IS 'SHA-256 (hex) of prev_hash and the row contents';
+
+-- ====================
+-- CHAIN HEAD
+-- ====================
+
+CREATE TABLE IF NOT EXISTS audit_log_chain_head (
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    last_seq BIGINT NOT NULL,
+    last_hash TEXT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+INSERT INTO audit_log_chain_head (id, last_seq, last_hash)
+VALUES (TRUE, 0, repeat('0', 64))
+ON CONFLICT (id) DO NOTHING;
+
+COMMENT ON TABLE audit_log_chain_head IS 'Last sequence number and hash of the audit log chain (single row)';
+
+-- ====================
+-- HASHING
+-- ====================
+
+CREATE OR REPLACE FUNCTION audit_log_chain_field(value TEXT)
+RETURNS TEXT AS $$
+    SELECT CASE
+        WHEN value IS NULL THEN '-'
+        ELSE octet_length(value)::TEXT || ':' || value
+    END;
+$$ LANGUAGE sql IMMUTABLE;
+
+-- SECURITY DEFINER so the application role can advance the head without
+-- holding UPDATE on it
+CREATE OR REPLACE FUNCTION chain_audit_log()
+RETURNS TRIGGER
+SECURITY DEFINER
+SET search_path = public
+AS $$
+DECLARE
+    head audit_log_chain_head%ROWTYPE;
+    canonical TEXT;
+BEGIN
+    SELECT * INTO head FROM audit_log_chain_head WHERE id FOR UPDATE;
+
+    NEW.chain_seq := head.last_seq + 1;
+    NEW.prev_hash := head.last_hash;
+
+    canonical := concat_ws('|',
+        audit_log_chain_field(NEW.prev_hash),
+        audit_log_chain_field(NEW.chain_seq::TEXT),
+        audit_log_chain_field(NEW.user_id::TEXT),
+        audit_log_chain_field(NEW.action),
+        audit_log_chain_field(NEW.entity_type),
+        audit_log_chain_field(NEW.entity_id),
+        audit_log_chain_field(NEW.changes::TEXT),
+        audit_log_chain_field(NEW.ip_address::TEXT),
+        audit_log_chain_field(NEW.user_agent),
+        audit_log_chain_field(NEW.request_id::TEXT),
+        audit_log_chain_field(NEW.impersonator_id::TEXT),
+        audit_log_chain_field(
+            to_char(NEW.created_at AT TIME ZONE 'UTC', 'YYYY-MM-DD"T"HH24:MI:SS.US"Z"')
+        )
+    );
+
+    NEW.row_hash := encode(sha256(convert_to(canonical, 'UTF8')), 'hex');
+
+    UPDATE audit_log_chain_head
+    SET last_seq = NEW.chain_seq,
+        last_hash = NEW.row_hash,
+        updated_at = NOW()
+    WHERE id;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS chain_audit_log_insert ON audit_logs;
+CREATE TRIGGER chain_audit_log_insert
+    BEFORE INSERT ON audit_logs
+    FOR EACH ROW
+    EXECUTE FUNCTION chain_audit_log();
+
+-- ====================
+-- PERMISSIONS
+-- ====================
+
+GRANT SELECT ON audit_log_chain_head TO mpms_user;
+
+-- ====================
+-- SETTINGS
+-- ====================
+
+INSERT INTO system_settings (
+    setting_key, setting_group, setting_name, setting_value,
+    value_type, description, default_value, is_public, is_encrypted, is_readonly
+) VALUES
+(
+    'audit_chain_verify_interval_hours',
+    'security',
+    'Audit Chain Verification Interval (hours)',
+    '24',
+    'INTEGER',
+    'How often the background job recomputes the audit log hash chain. 0 disables the job; the verify endpoint remains available.',
+    '24',
+    false,
+    false,
+    false
+)
+ON CONFLICT (setting_key) DO NOTHING;
@@ -0,0 +1,56 @@
+-- Migration: Patient Data Exports
+-- Date: 2026-03-20
+--
+-- GDPR subject access requests: POST /api/v1/patients/{id}/export builds a
+-- machine-readable bundle of everything stored about a patient (JSON plus the
+-- generated PDFs) in the background, as an AES-256 encrypted zip. Each
+-- request is tracked here; the passphrase is never stored.
+--
+-- Bundles are deleted from storage once expires_at has passed.
+
+CREATE TABLE IF NOT EXISTS patient_data_exports (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    patient_id UUID NOT NULL REFERENCES patients(id) ON DELETE CASCADE,
+    requested_by UUID NOT NULL REFERENCES users(id),
+    status VARCHAR(20) NOT NULL DEFAULT 'PROCESSING' CHECK (
+        status IN ('PROCESSING', 'COMPLETED', 'FAILED', 'EXPIRED')
+    ),
+    file_path TEXT,
+    file_size_bytes BIGINT,
+    file_hash VARCHAR(64),
+    error_message TEXT,
+    expires_at TIMESTAMPTZ,
+    download_count INT NOT NULL DEFAULT 0,
+    last_downloaded_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_patient_data_exports_patient
+    ON patient_data_exports (patient_id, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_patient_data_exports_expires
+    ON patient_data_exports (expires_at)
+    WHERE status = 'COMPLETED';
+
+COMMENT ON TABLE patient_data_exports IS 'GDPR subject access export bundles (encrypted zip per request)';
+COMMENT ON COLUMN patient_data_exports.file_hash IS 'SHA-256 (hex) of the encrypted zip';
+
+GRANT SELECT, INSERT, UPDATE ON patient_data_exports TO mpms_user;
+
+INSERT INTO system_settings (
+    setting_key, setting_group, setting_name, setting_value,
+    value_type, description, default_value, is_public, is_encrypted, is_readonly
+) VALUES
+(
+    'patient_export_retention_days',
+    'security',
+    'Patient Export Retention (days)',
+    '7',
+    'INTEGER',
+    'Days a GDPR subject access export stays downloadable before the bundle is deleted from storage.',
+    '7',
+    false,
+    false,
+    false
+)
+ON CONFLICT (setting_key) DO NOTHING;
@@ -0,0 +1,135 @@
+-- Migration: Patient Erasure Requests
+-- Date: 2026-03-21
+--
+-- GDPR right to erasure (Art. 17) with four-eyes review. An admin files an
+-- erasure request for a patient; a different admin approves or rejects it.
+-- Approval anonymizes the patient in a single transaction:
+-- - patients: name replaced by a placeholder, date of birth truncated to the
+--   year, fiscal code, contacts, address, emergency contact, notes and photo
+--   cleared; the row is tombstoned with anonymized_at and can no longer be
+--   modified
+-- - patient_insurance and patient_notification_preferences: deleted
+-- - notification_queue: unsent messages cancelled, recipient and content
+--   of every message scrubbed
+-- - appointments: future ones cancelled; past ones kept
+-- - generated_documents: marked deleted, PDFs and generation data removed
+-- - patient_data_exports: bundles deleted
+-- Visits, diagnoses, prescriptions and audit logs are kept: medical records
+-- must be retained by law, and are linked to the patient only by id once the
+-- identifying fields are gone.
+
+-- ====================
+-- PATIENT TOMBSTONE
+-- ====================
+
+ALTER TABLE patients
+    ADD COLUMN IF NOT EXISTS anonymized_at TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS anonymized_by UUID REFERENCES users(id);
+
+COMMENT ON COLUMN patients.anonymized_at IS 'When identifying fields were erased (GDPR Art. 17); the row is read-only afterwards';
+
+CREATE OR REPLACE FUNCTION prevent_anonymized_patient_modification()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF OLD.anonymized_at IS NOT NULL THEN
+        RAISE EXCEPTION 'Patient % has been anonymized and cannot be modified', OLD.id;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trigger_prevent_anonymized_patient_modification ON patients;
+CREATE TRIGGER trigger_prevent_anonymized_patient_modification
+    BEFORE UPDATE ON patients
+    FOR EACH ROW
+    EXECUTE FUNCTION prevent_anonymized_patient_modification();
+
+-- New appointments and notifications would re-attach contact details to an
+-- anonymized patient
+CREATE OR REPLACE FUNCTION prevent_anonymized_patient_reference()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF NEW.patient_id IS NOT NULL AND EXISTS (
+        SELECT 1 FROM patients WHERE id = NEW.patient_id AND anonymized_at IS NOT NULL
+    ) THEN
+        RAISE EXCEPTION 'Patient % has been anonymized', NEW.patient_id;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trigger_prevent_anonymized_patient_appointment ON appointments;
+CREATE TRIGGER trigger_prevent_anonymized_patient_appointment
+    BEFORE INSERT ON appointments
+    FOR EACH ROW
+    EXECUTE FUNCTION prevent_anonymized_patient_reference();
+
+DROP TRIGGER IF EXISTS trigger_prevent_anonymized_patient_notification ON notification_queue;
+CREATE TRIGGER trigger_prevent_anonymized_patient_notification
+    BEFORE INSERT ON notification_queue
+    FOR EACH ROW
+    EXECUTE FUNCTION prevent_anonymized_patient_reference();
+
+-- ====================
+-- ERASURE REQUESTS
+-- ====================
+
+CREATE TABLE IF NOT EXISTS patient_erasure_requests (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    patient_id UUID NOT NULL REFERENCES patients(id),
+    reason TEXT NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'PENDING' CHECK (
+        status IN ('PENDING', 'COMPLETED', 'REJECTED')
+    ),
+    requested_by UUID NOT NULL REFERENCES users(id),
+    requested_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    reviewed_by UUID REFERENCES users(id),
+    reviewed_at TIMESTAMPTZ,
+    review_notes TEXT,
+    completed_at TIMESTAMPTZ,
+    -- What was erased, per table (counts only)
+    summary JSONB,
+
+    CONSTRAINT patient_erasure_four_eyes CHECK (reviewed_by IS NULL OR reviewed_by <> requested_by),
+    CONSTRAINT patient_erasure_review CHECK (
+        (status = 'PENDING' AND reviewed_by IS NULL) OR
+        (status <> 'PENDING' AND reviewed_by IS NOT NULL AND reviewed_at IS NOT NULL)
+    )
+);
+
+-- At most one open request per patient
+CREATE UNIQUE INDEX IF NOT EXISTS idx_patient_erasure_requests_pending
+    ON patient_erasure_requests (patient_id)
+    WHERE status = 'PENDING';
+CREATE INDEX IF NOT EXISTS idx_patient_erasure_requests_requested
+    ON patient_erasure_requests (requested_at DESC);
+
+COMMENT ON TABLE patient_erasure_requests IS 'GDPR erasure requests; approval by a second admin anonymizes the patient';
+
+GRANT SELECT, INSERT, UPDATE ON patient_erasure_requests TO mpms_user;
+
+-- ====================
+-- ERASURE RLS POLICIES
+-- ====================
+
+-- Admins may rewrite or delete another provider's rows only for the patient
+-- being erased, named by the transaction-local app.erasure_patient_id
+
+DROP POLICY IF EXISTS appointments_erasure_update_policy ON appointments;
+CREATE POLICY appointments_erasure_update_policy ON appointments
+    FOR UPDATE
+    USING (is_admin() AND patient_id::TEXT = current_setting('app.erasure_patient_id', TRUE))
+    WITH CHECK (is_admin() AND patient_id::TEXT = current_setting('app.erasure_patient_id', TRUE));
+
+DROP POLICY IF EXISTS generated_documents_erasure_update_policy ON generated_documents;
+CREATE POLICY generated_documents_erasure_update_policy ON generated_documents
+    FOR UPDATE
+    USING (is_admin() AND patient_id::TEXT = current_setting('app.erasure_patient_id', TRUE))
+    WITH CHECK (is_admin() AND patient_id::TEXT = current_setting('app.erasure_patient_id', TRUE));
+
+DROP POLICY IF EXISTS patient_insurance_erasure_delete_policy ON patient_insurance;
+CREATE POLICY patient_insurance_erasure_delete_policy ON patient_insurance
+    FOR DELETE
+    USING (is_admin() AND patient_id::TEXT = current_setting('app.erasure_patient_id', TRUE));
+
+GRANT DELETE ON patient_insurance, patient_notification_preferences TO mpms_user;
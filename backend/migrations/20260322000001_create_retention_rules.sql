@@ -0,0 +1,170 @@
+-- Migration: Data Retention Rules
+-- Date: 2026-03-22
+--
+-- Configurable retention per entity type, applied by a scheduled job
+-- (services/retention_service.rs). Each rule purges records older than
+-- retention_days:
+-- - GENERATED_DOCUMENTS: document rows and their PDFs
+-- - AUDIT_LOGS: whole monthly partitions that ended before the cutoff
+-- - NOTIFICATIONS: sent, failed and cancelled notifications
+-- - PATIENT_DATA_EXPORTS: finished export records (bundles already expire
+--   after patient_export_retention_days)
+--
+-- Rules are seeded disabled and in dry-run mode: a dry run only counts what
+-- would be purged. Every run is recorded in retention_runs and audit_logs.
+
+-- ====================
+-- RULES
+-- ====================
+
+CREATE TABLE IF NOT EXISTS retention_rules (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    entity_type VARCHAR(50) NOT NULL UNIQUE CHECK (
+        entity_type IN ('GENERATED_DOCUMENTS', 'AUDIT_LOGS', 'NOTIFICATIONS', 'PATIENT_DATA_EXPORTS')
+    ),
+    retention_days INTEGER NOT NULL CHECK (retention_days >= 30),
+    is_enabled BOOLEAN NOT NULL DEFAULT FALSE,
+    -- Scheduled runs only report what would be purged
+    dry_run BOOLEAN NOT NULL DEFAULT TRUE,
+    description TEXT,
+    last_run_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_by UUID REFERENCES users(id)
+);
+
+COMMENT ON TABLE retention_rules IS 'Retention period per entity type, applied by the retention job';
+
+INSERT INTO retention_rules (entity_type, retention_days, description) VALUES
+    ('GENERATED_DOCUMENTS', 3650, 'Generated documents and their PDFs, 10 years after generation'),
+    ('AUDIT_LOGS', 2190, 'Audit log partitions, 6 years after the end of the month'),
+    ('NOTIFICATIONS', 730, 'Sent, failed and cancelled notifications, 2 years after queueing'),
+    ('PATIENT_DATA_EXPORTS', 365, 'Finished GDPR export records, 1 year after the request')
+ON CONFLICT (entity_type) DO NOTHING;
+
+-- ====================
+-- RUNS
+-- ====================
+
+CREATE TABLE IF NOT EXISTS retention_runs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    rule_id UUID NOT NULL REFERENCES retention_rules(id) ON DELETE CASCADE,
+    entity_type VARCHAR(50) NOT NULL,
+    dry_run BOOLEAN NOT NULL,
+    cutoff TIMESTAMPTZ NOT NULL,
+    -- Records older than the cutoff
+    eligible_count BIGINT NOT NULL DEFAULT 0,
+    -- Records actually purged (0 for dry runs)
+    purged_count BIGINT NOT NULL DEFAULT 0,
+    status VARCHAR(20) NOT NULL CHECK (status IN ('COMPLETED', 'FAILED')),
+    error_message TEXT,
+    -- NULL when run by the scheduler
+    triggered_by UUID REFERENCES users(id),
+    started_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_retention_runs_rule_started
+    ON retention_runs (rule_id, started_at DESC);
+CREATE INDEX IF NOT EXISTS idx_retention_runs_started
+    ON retention_runs (started_at DESC);
+
+COMMENT ON TABLE retention_runs IS 'History of retention rule executions, including dry runs';
+
+GRANT SELECT, INSERT, UPDATE ON retention_rules TO mpms_user;
+GRANT SELECT, INSERT, UPDATE ON retention_runs TO mpms_user;
+
+-- ====================
+-- PURGE PERMISSIONS
+-- ====================
+
+DROP POLICY IF EXISTS generated_documents_retention_delete_policy ON generated_documents;
+CREATE POLICY generated_documents_retention_delete_policy ON generated_documents
+    FOR DELETE
+    USING (is_admin());
+
+GRANT DELETE ON generated_documents, notification_queue, patient_data_exports TO mpms_user;
+
+-- ====================
+-- AUDIT LOG PARTITIONS
+-- ====================
+
+-- Audit rows cannot be deleted (prevent_audit_log_delete), so audit retention
+-- drops whole monthly partitions. The last dropped chain position is kept in
+-- the chain head so verification starts after it instead of reporting a gap.
+ALTER TABLE audit_log_chain_head
+    ADD COLUMN IF NOT EXISTS pruned_seq BIGINT NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS pruned_hash TEXT NOT NULL DEFAULT repeat('0', 64);
+
+COMMENT ON COLUMN audit_log_chain_head.pruned_seq IS 'Last chain sequence removed by retention (0 if none)';
+
+-- Returns the partitions that ended on or before p_cutoff with their row
+-- counts, and drops them unless p_dry_run. SECURITY DEFINER: the application
+-- role does not own audit_logs.
+CREATE OR REPLACE FUNCTION purge_audit_log_partitions(p_cutoff TIMESTAMPTZ, p_dry_run BOOLEAN)
+RETURNS TABLE (partition_name TEXT, row_count BIGINT)
+SECURITY DEFINER
+SET search_path = public
+AS $$
+DECLARE
+    part RECORD;
+    last_row RECORD;
+BEGIN
+    FOR part IN
+        SELECT c.relname::TEXT AS name,
+               substring(pg_get_expr(c.relpartbound, c.oid) FROM 'TO \(''([^'']+)''\)')::TIMESTAMPTZ AS upper_bound
+        FROM pg_inherits i
+        JOIN pg_class c ON c.oid = i.inhrelid
+        WHERE i.inhparent = 'audit_logs'::regclass
+        ORDER BY 2
+    LOOP
+        CONTINUE WHEN part.upper_bound IS NULL OR part.upper_bound > p_cutoff;
+
+        partition_name := part.name;
+        EXECUTE format('SELECT COUNT(*) FROM %I', part.name) INTO row_count;
+
+        IF NOT p_dry_run THEN
+            EXECUTE format(
+                'SELECT chain_seq, row_hash FROM %I WHERE chain_seq IS NOT NULL ORDER BY chain_seq DESC LIMIT 1',
+                part.name
+            ) INTO last_row;
+
+            IF last_row.chain_seq IS NOT NULL THEN
+                UPDATE audit_log_chain_head
+                SET pruned_seq = last_row.chain_seq,
+                    pruned_hash = last_row.row_hash
+                WHERE id AND pruned_seq < last_row.chain_seq;
+            END IF;
+
+            EXECUTE format('DROP TABLE %I', part.name);
+        END IF;
+
+        RETURN NEXT;
+    END LOOP;
+END;
+$$ LANGUAGE plpgsql;
+
+REVOKE ALL ON FUNCTION purge_audit_log_partitions(TIMESTAMPTZ, BOOLEAN) FROM PUBLIC;
+GRANT EXECUTE ON FUNCTION purge_audit_log_partitions(TIMESTAMPTZ, BOOLEAN) TO mpms_user;
+
+-- ====================
+-- SETTINGS
+-- ====================
+
+INSERT INTO system_settings (
+    setting_key, setting_group, setting_name, setting_value,
+    value_type, description, default_value, is_public, is_encrypted, is_readonly
+) VALUES
+(
+    'retention_job_interval_hours',
+    'security',
+    'Retention Job Interval (hours)',
+    '24',
+    'INTEGER',
+    'Hours between scheduled runs of the enabled retention rules. 0 disables the scheduled job.',
+    '24',
+    false,
+    false,
+    false
+)
+ON CONFLICT (setting_key) DO NOTHING;
@@ -0,0 +1,175 @@
+-- Migration: Patient Consents
+-- Date: 2026-03-23
+--
+-- Consent management for data processing, marketing, telemedicine and
+-- third-party sharing:
+-- - consent_texts: versioned wording of each consent type; published
+--   versions are never edited, a change is a new version
+-- - patient_consents: a patient's consent to a specific text version, with
+--   signature, optional expiry and withdrawal. At most one GRANTED consent
+--   per patient and type; recording a new one supersedes the previous.
+-- Marketing notifications require an active MARKETING consent, checked when
+-- they are queued and again before they are sent.
+
+-- ====================
+-- CONSENT TEXTS
+-- ====================
+
+CREATE TABLE IF NOT EXISTS consent_texts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    consent_type VARCHAR(30) NOT NULL CHECK (
+        consent_type IN ('DATA_PROCESSING', 'MARKETING', 'TELEMEDICINE', 'THIRD_PARTY_SHARING')
+    ),
+    version INTEGER NOT NULL CHECK (version >= 1),
+    title VARCHAR(255) NOT NULL,
+    body TEXT NOT NULL,
+    -- Days a consent to this text stays valid (NULL = until withdrawn)
+    validity_days INTEGER CHECK (validity_days IS NULL OR validity_days >= 1),
+    created_by UUID REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT consent_texts_type_version_unique UNIQUE (consent_type, version)
+);
+
+COMMENT ON TABLE consent_texts IS 'Versioned consent wording; rows are immutable once published';
+
+-- Published texts are what patients signed: no edits, no deletes
+GRANT SELECT, INSERT ON consent_texts TO mpms_user;
+
+-- ====================
+-- PATIENT CONSENTS
+-- ====================
+
+CREATE TABLE IF NOT EXISTS patient_consents (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    patient_id UUID NOT NULL REFERENCES patients(id) ON DELETE CASCADE,
+    consent_type VARCHAR(30) NOT NULL,
+    consent_text_id UUID NOT NULL REFERENCES consent_texts(id),
+    status VARCHAR(20) NOT NULL DEFAULT 'GRANTED' CHECK (
+        status IN ('GRANTED', 'WITHDRAWN', 'SUPERSEDED')
+    ),
+    granted_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ,
+
+    -- How the patient signed
+    signature_method VARCHAR(20) NOT NULL CHECK (
+        signature_method IN ('HANDWRITTEN', 'ELECTRONIC', 'VERBAL', 'PAPER')
+    ),
+    -- 🔒 Encrypted signature image (data URL) for HANDWRITTEN
+    signature_data TEXT,
+    -- Who signed, when not the patient (e.g. parent or legal guardian)
+    signer_name VARCHAR(255),
+    signer_relationship VARCHAR(50),
+
+    withdrawn_at TIMESTAMPTZ,
+    withdrawn_by UUID REFERENCES users(id),
+    withdrawal_reason TEXT,
+
+    recorded_by UUID NOT NULL REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT patient_consents_signature CHECK (
+        signature_method <> 'HANDWRITTEN' OR signature_data IS NOT NULL
+    ),
+    CONSTRAINT patient_consents_withdrawal CHECK (
+        (status = 'WITHDRAWN') = (withdrawn_at IS NOT NULL)
+    ),
+    CONSTRAINT patient_consents_expiry CHECK (expires_at IS NULL OR expires_at > granted_at)
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_patient_consents_active
+    ON patient_consents (patient_id, consent_type)
+    WHERE status = 'GRANTED';
+CREATE INDEX IF NOT EXISTS idx_patient_consents_patient
+    ON patient_consents (patient_id, granted_at DESC);
+
+COMMENT ON TABLE patient_consents IS 'Patient consents per type, with signature, expiry and withdrawal';
+
+CREATE TRIGGER update_patient_consents_updated_at
+    BEFORE UPDATE ON patient_consents
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
+
+-- Consent type must match the text it refers to
+CREATE OR REPLACE FUNCTION check_patient_consent_text_type()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF NOT EXISTS (
+        SELECT 1 FROM consent_texts
+        WHERE id = NEW.consent_text_id AND consent_type = NEW.consent_type
+    ) THEN
+        RAISE EXCEPTION 'Consent text % is not a % text', NEW.consent_text_id, NEW.consent_type;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trigger_check_patient_consent_text_type
+    BEFORE INSERT ON patient_consents
+    FOR EACH ROW
+    EXECUTE FUNCTION check_patient_consent_text_type();
+
+-- No new consents for erased patients
+CREATE TRIGGER trigger_prevent_anonymized_patient_consent
+    BEFORE INSERT ON patient_consents
+    FOR EACH ROW
+    EXECUTE FUNCTION prevent_anonymized_patient_reference();
+
+-- ====================
+-- RLS
+-- ====================
+
+ALTER TABLE patient_consents ENABLE ROW LEVEL SECURITY;
+ALTER TABLE patient_consents FORCE ROW LEVEL SECURITY;
+
+CREATE POLICY patient_consents_select_policy ON patient_consents
+    FOR SELECT
+    USING (is_doctor() OR is_nurse() OR is_receptionist());
+
+CREATE POLICY patient_consents_insert_policy ON patient_consents
+    FOR INSERT
+    WITH CHECK (is_doctor() OR is_receptionist());
+
+CREATE POLICY patient_consents_update_policy ON patient_consents
+    FOR UPDATE
+    USING (is_doctor() OR is_receptionist())
+    WITH CHECK (is_doctor() OR is_receptionist());
+
+GRANT SELECT, INSERT, UPDATE ON patient_consents TO mpms_user;
+
+-- ====================
+-- MARKETING NOTIFICATIONS
+-- ====================
+
+ALTER TABLE notification_queue
+    DROP CONSTRAINT IF EXISTS notification_queue_notification_type_check;
+ALTER TABLE notification_queue
+    ADD CONSTRAINT notification_queue_notification_type_check CHECK (
+        notification_type IN ('APPOINTMENT_REMINDER', 'APPOINTMENT_BOOKED', 'APPOINTMENT_CONFIRMATION',
+                              'APPOINTMENT_CANCELLATION', 'VISIT_SUMMARY', 'PRESCRIPTION_READY',
+                              'FOLLOW_UP_REMINDER', 'CUSTOM', 'MARKETING')
+    );
+
+-- ====================
+-- CASBIN POLICIES
+-- ====================
+-- Same rules as casbin/policy.csv, for installs that already seeded the table
+
+INSERT INTO casbin_rules (ptype, v0, v1, v2)
+SELECT v.ptype, v.v0, v.v1, v.v2
+FROM (VALUES
+    ('p', 'ADMIN', 'consents', 'create'),
+    ('p', 'ADMIN', 'consents', 'read'),
+    ('p', 'ADMIN', 'consents', 'withdraw'),
+    ('p', 'ADMIN', 'consents', 'manage_texts'),
+    ('p', 'DOCTOR', 'consents', 'create'),
+    ('p', 'DOCTOR', 'consents', 'read'),
+    ('p', 'DOCTOR', 'consents', 'withdraw'),
+    ('p', 'NURSE', 'consents', 'read'),
+    ('p', 'RECEPTIONIST', 'consents', 'create'),
+    ('p', 'RECEPTIONIST', 'consents', 'read'),
+    ('p', 'RECEPTIONIST', 'consents', 'withdraw')
+) AS v (ptype, v0, v1, v2)
+WHERE EXISTS (SELECT 1 FROM casbin_rules)
+ON CONFLICT (ptype, v0, v1, v2, v3, v4, v5) DO NOTHING;
@@ -0,0 +1,24 @@
+-- Migration: Research Dataset Export Settings
+-- Date: 2026-03-24
+--
+-- Minimum k for k-anonymized research dataset exports
+-- (POST /api/v1/reports/research-export). Requests asking for a smaller k
+-- are rejected.
+
+INSERT INTO system_settings (
+    setting_key, setting_group, setting_name, setting_value,
+    value_type, description, default_value, is_public, is_encrypted, is_readonly
+) VALUES
+(
+    'research_export_min_k',
+    'security',
+    'Research Export Minimum k',
+    '5',
+    'INTEGER',
+    'Smallest equivalence class allowed in anonymized research exports: every combination of age band, gender, region and period must be shared by at least this many patients.',
+    '5',
+    false,
+    false,
+    false
+)
+ON CONFLICT (setting_key) DO NOTHING;
@@ -0,0 +1,143 @@
+-- Migration: Patient Merge
+-- Date: 2026-03-25
+--
+-- Merging a duplicate patient record into the surviving one
+-- (POST /api/v1/patients/:id/merge/:merge_id). In a single transaction the
+-- duplicate's appointments, visits, diagnoses, prescriptions, documents,
+-- insurance and notifications are re-parented to the surviving patient, and
+-- the duplicate is tombstoned with merged_into_id and becomes read-only.
+-- Insurance of a type the surviving patient already has stays on the
+-- tombstone. Consents, notification preferences, delegations and exports
+-- are not moved.
+
+-- ====================
+-- PATIENT TOMBSTONE
+-- ====================
+
+ALTER TABLE patients
+    ADD COLUMN IF NOT EXISTS merged_into_id UUID REFERENCES patients(id),
+    ADD COLUMN IF NOT EXISTS merged_at TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS merged_by UUID REFERENCES users(id);
+
+ALTER TABLE patients
+    DROP CONSTRAINT IF EXISTS patients_merge_tombstone;
+ALTER TABLE patients
+    ADD CONSTRAINT patients_merge_tombstone CHECK (
+        (merged_into_id IS NULL) = (merged_at IS NULL)
+        AND (merged_into_id IS NULL OR merged_into_id <> id)
+    );
+
+CREATE INDEX IF NOT EXISTS idx_patients_merged_into
+    ON patients (merged_into_id)
+    WHERE merged_into_id IS NOT NULL;
+
+COMMENT ON COLUMN patients.merged_into_id IS 'Surviving patient this duplicate was merged into; the row is read-only afterwards';
+
+-- A merged record only changes again to be erased
+CREATE OR REPLACE FUNCTION prevent_merged_patient_modification()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF OLD.merged_at IS NOT NULL
+       AND NOT (OLD.anonymized_at IS NULL AND NEW.anonymized_at IS NOT NULL) THEN
+        RAISE EXCEPTION 'Patient % has been merged into % and cannot be modified',
+            OLD.id, OLD.merged_into_id;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trigger_prevent_merged_patient_modification ON patients;
+CREATE TRIGGER trigger_prevent_merged_patient_modification
+    BEFORE UPDATE ON patients
+    FOR EACH ROW
+    EXECUTE FUNCTION prevent_merged_patient_modification();
+
+-- New appointments, notifications and consents go to the surviving record
+CREATE OR REPLACE FUNCTION prevent_anonymized_patient_reference()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF NEW.patient_id IS NOT NULL AND EXISTS (
+        SELECT 1 FROM patients WHERE id = NEW.patient_id AND anonymized_at IS NOT NULL
+    ) THEN
+        RAISE EXCEPTION 'Patient % has been anonymized', NEW.patient_id;
+    END IF;
+    IF NEW.patient_id IS NOT NULL AND EXISTS (
+        SELECT 1 FROM patients WHERE id = NEW.patient_id AND merged_at IS NOT NULL
+    ) THEN
+        RAISE EXCEPTION 'Patient % has been merged into another record', NEW.patient_id;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+-- ====================
+-- SIGNED VISITS
+-- ====================
+
+-- Same rules as before, except that a merge may move a signed or locked
+-- visit to the surviving patient as long as nothing else changes
+CREATE OR REPLACE FUNCTION prevent_signed_visit_modification()
+RETURNS TRIGGER AS $$
+BEGIN
+    -- Allow re-parenting by a patient merge
+    IF OLD.patient_id::TEXT = current_setting('app.merge_patient_id', TRUE)
+       AND NEW.patient_id::TEXT = current_setting('app.merge_into_patient_id', TRUE)
+       AND (to_jsonb(NEW) - 'patient_id' - 'updated_at') = (to_jsonb(OLD) - 'patient_id' - 'updated_at') THEN
+        RETURN NEW;
+    END IF;
+
+    -- Allow status transitions from DRAFT to SIGNED
+    IF OLD.status = 'DRAFT' AND NEW.status = 'SIGNED' THEN
+        RETURN NEW;
+    END IF;
+
+    -- Allow status transitions from SIGNED to LOCKED
+    IF OLD.status = 'SIGNED' AND NEW.status = 'LOCKED' THEN
+        RETURN NEW;
+    END IF;
+
+    -- Prevent any changes to SIGNED visits except locking
+    IF OLD.status = 'SIGNED' AND NEW.status != 'LOCKED' THEN
+        RAISE EXCEPTION 'Cannot modify signed visit. Create a new version or lock it.';
+    END IF;
+
+    -- Prevent any changes to LOCKED visits
+    IF OLD.status = 'LOCKED' THEN
+        RAISE EXCEPTION 'Cannot modify locked visit. Visit is permanently locked.';
+    END IF;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+-- ====================
+-- MERGE RLS POLICIES
+-- ====================
+
+-- Admins may re-parent another provider's rows only from the duplicate to
+-- the surviving patient, named by the transaction-local app.merge_patient_id
+-- and app.merge_into_patient_id
+
+DROP POLICY IF EXISTS appointments_merge_update_policy ON appointments;
+CREATE POLICY appointments_merge_update_policy ON appointments
+    FOR UPDATE
+    USING (is_admin() AND patient_id::TEXT = current_setting('app.merge_patient_id', TRUE))
+    WITH CHECK (is_admin() AND patient_id::TEXT = current_setting('app.merge_into_patient_id', TRUE));
+
+DROP POLICY IF EXISTS visits_merge_update_policy ON visits;
+CREATE POLICY visits_merge_update_policy ON visits
+    FOR UPDATE
+    USING (is_admin() AND patient_id::TEXT = current_setting('app.merge_patient_id', TRUE))
+    WITH CHECK (is_admin() AND patient_id::TEXT = current_setting('app.merge_into_patient_id', TRUE));
+
+DROP POLICY IF EXISTS prescriptions_merge_update_policy ON prescriptions;
+CREATE POLICY prescriptions_merge_update_policy ON prescriptions
+    FOR UPDATE
+    USING (is_admin() AND patient_id::TEXT = current_setting('app.merge_patient_id', TRUE))
+    WITH CHECK (is_admin() AND patient_id::TEXT = current_setting('app.merge_into_patient_id', TRUE));
+
+DROP POLICY IF EXISTS generated_documents_merge_update_policy ON generated_documents;
+CREATE POLICY generated_documents_merge_update_policy ON generated_documents
+    FOR UPDATE
+    USING (is_admin() AND patient_id::TEXT = current_setting('app.merge_patient_id', TRUE))
+    WITH CHECK (is_admin() AND patient_id::TEXT = current_setting('app.merge_into_patient_id', TRUE));
@@ -0,0 +1,22 @@
+-- Migration: Patient Photos
+-- Date: 2026-03-26
+--
+-- Identification photo on the patient record (PUT/GET/DELETE
+-- /api/v1/patients/:id/photo, GET /api/v1/patients/:id/photo/thumbnail).
+-- The photo and its thumbnail are stored through uploaded_files, encrypted
+-- at rest, and are never listed or served by the generic /files endpoints.
+-- patients.photo_url keeps pointing to the photo endpoint.
+
+ALTER TYPE file_purpose ADD VALUE IF NOT EXISTS 'PATIENT_PHOTO';
+
+ALTER TABLE uploaded_files
+    ADD COLUMN IF NOT EXISTS is_encrypted BOOLEAN NOT NULL DEFAULT false;
+
+COMMENT ON COLUMN uploaded_files.is_encrypted IS 'Content on disk is AES-256-GCM encrypted; file_size_bytes and content_hash are of the plaintext';
+
+ALTER TABLE patients
+    ADD COLUMN IF NOT EXISTS photo_file_id UUID REFERENCES uploaded_files(id),
+    ADD COLUMN IF NOT EXISTS photo_thumbnail_file_id UUID REFERENCES uploaded_files(id);
+
+COMMENT ON COLUMN patients.photo_file_id IS 'Encrypted identification photo (uploaded_files, purpose PATIENT_PHOTO)';
+COMMENT ON COLUMN patients.photo_thumbnail_file_id IS 'Encrypted JPEG thumbnail of photo_file_id';
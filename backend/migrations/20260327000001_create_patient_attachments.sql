@@ -0,0 +1,100 @@
+-- Migration: Patient Attachments
+-- Date: 2026-03-27
+--
+-- External documents on the patient record (old lab reports, referral
+-- letters, discharge summaries, scans), optionally linked to a visit. The
+-- file itself is stored through uploaded_files, encrypted at rest, and is
+-- only reachable through /api/v1/patients/:id/attachments. Every download is
+-- recorded in the audit log. Attachments are medical records: deleting one
+-- only marks it deleted, and an erasure keeps them like visits.
+
+ALTER TYPE file_purpose ADD VALUE IF NOT EXISTS 'PATIENT_ATTACHMENT';
+
+CREATE TABLE IF NOT EXISTS patient_attachments (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    patient_id UUID NOT NULL REFERENCES patients(id) ON DELETE CASCADE,
+    visit_id UUID REFERENCES visits(id) ON DELETE SET NULL,
+    file_id UUID NOT NULL REFERENCES uploaded_files(id),
+    category VARCHAR(30) NOT NULL CHECK (
+        category IN ('LAB_REPORT', 'REFERRAL_LETTER', 'IMAGING_REPORT', 'DISCHARGE_SUMMARY',
+                     'SPECIALIST_REPORT', 'SCANNED_DOCUMENT', 'OTHER')
+    ),
+    title VARCHAR(255) NOT NULL,
+    description TEXT,
+    -- Date of the document itself (e.g. when the lab report was issued)
+    document_date DATE,
+
+    created_by UUID NOT NULL REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_by UUID REFERENCES users(id),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    deleted_at TIMESTAMPTZ,
+    deleted_by UUID REFERENCES users(id),
+
+    CONSTRAINT patient_attachments_deletion CHECK ((deleted_at IS NULL) = (deleted_by IS NULL))
+);
+
+CREATE INDEX IF NOT EXISTS idx_patient_attachments_patient
+    ON patient_attachments (patient_id, document_date DESC NULLS LAST, created_at DESC)
+    WHERE deleted_at IS NULL;
+CREATE INDEX IF NOT EXISTS idx_patient_attachments_visit
+    ON patient_attachments (visit_id)
+    WHERE visit_id IS NOT NULL AND deleted_at IS NULL;
+
+COMMENT ON TABLE patient_attachments IS 'External documents on the patient record; content encrypted in uploaded_files';
+
+CREATE TRIGGER update_patient_attachments_updated_at
+    BEFORE UPDATE ON patient_attachments
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
+
+-- No new attachments for erased or merged patients
+CREATE TRIGGER trigger_prevent_anonymized_patient_attachment
+    BEFORE INSERT ON patient_attachments
+    FOR EACH ROW
+    EXECUTE FUNCTION prevent_anonymized_patient_reference();
+
+-- ====================
+-- RLS
+-- ====================
+
+ALTER TABLE patient_attachments ENABLE ROW LEVEL SECURITY;
+ALTER TABLE patient_attachments FORCE ROW LEVEL SECURITY;
+
+CREATE POLICY patient_attachments_select_policy ON patient_attachments
+    FOR SELECT
+    USING (is_doctor() OR is_nurse());
+
+CREATE POLICY patient_attachments_insert_policy ON patient_attachments
+    FOR INSERT
+    WITH CHECK (is_doctor() OR is_nurse());
+
+-- Metadata edits, soft deletes and patient merges (ADMIN)
+CREATE POLICY patient_attachments_update_policy ON patient_attachments
+    FOR UPDATE
+    USING (is_doctor())
+    WITH CHECK (is_doctor());
+
+GRANT SELECT, INSERT, UPDATE ON patient_attachments TO mpms_user;
+
+-- ====================
+-- CASBIN POLICIES
+-- ====================
+-- Same rules as casbin/policy.csv, for installs that already seeded the table
+
+INSERT INTO casbin_rules (ptype, v0, v1, v2)
+SELECT v.ptype, v.v0, v.v1, v.v2
+FROM (VALUES
+    ('p', 'ADMIN', 'attachments', 'create'),
+    ('p', 'ADMIN', 'attachments', 'read'),
+    ('p', 'ADMIN', 'attachments', 'update'),
+    ('p', 'ADMIN', 'attachments', 'delete'),
+    ('p', 'DOCTOR', 'attachments', 'create'),
+    ('p', 'DOCTOR', 'attachments', 'read'),
+    ('p', 'DOCTOR', 'attachments', 'update'),
+    ('p', 'DOCTOR', 'attachments', 'delete'),
+    ('p', 'NURSE', 'attachments', 'create'),
+    ('p', 'NURSE', 'attachments', 'read')
+) AS v (ptype, v0, v1, v2)
+WHERE EXISTS (SELECT 1 FROM casbin_rules)
+ON CONFLICT (ptype, v0, v1, v2, v3, v4, v5) DO NOTHING;
@@ -0,0 +1,27 @@
+-- Migration: Attachment OCR
+-- Date: 2026-03-28
+--
+-- Text recognition of patient attachments, run by a background job when
+-- OCR_PROVIDER is set. The recognized text makes attachments searchable and
+-- is used to suggest the document date and the issuing laboratory. Both the
+-- text and the suggestions are patient data and are stored encrypted.
+
+ALTER TABLE patient_attachments
+    ADD COLUMN IF NOT EXISTS ocr_status VARCHAR(20) NOT NULL DEFAULT 'PENDING' CHECK (
+        ocr_status IN ('PENDING', 'PROCESSING', 'COMPLETED', 'FAILED', 'SKIPPED')
+    ),
+    -- 🔒 Encrypted recognized text
+    ADD COLUMN IF NOT EXISTS ocr_text TEXT,
+    -- 🔒 Encrypted JSON: {"document_date": ..., "lab_name": ...}
+    ADD COLUMN IF NOT EXISTS ocr_suggestions TEXT,
+    ADD COLUMN IF NOT EXISTS ocr_attempts INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS ocr_error TEXT,
+    ADD COLUMN IF NOT EXISTS ocr_started_at TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS ocr_completed_at TIMESTAMPTZ;
+
+-- Queue of the OCR job
+CREATE INDEX IF NOT EXISTS idx_patient_attachments_ocr_queue
+    ON patient_attachments (created_at)
+    WHERE ocr_status IN ('PENDING', 'PROCESSING') AND deleted_at IS NULL;
+
+COMMENT ON COLUMN patient_attachments.ocr_text IS 'Encrypted OCR text of the document';
@@ -0,0 +1,105 @@
+-- Migration: Patient Allergies
+-- Date: 2026-03-29
+--
+-- Structured allergies and intolerances (substance, reaction, severity,
+-- onset, status), replacing the free-text patients.allergies array for new
+-- records. The free-text list is kept for existing patients and shown next
+-- to the structured entries. Substance, reaction and notes are encrypted.
+-- Entries are never removed: an entry recorded by mistake is marked
+-- ENTERED_IN_ERROR or deleted, which only marks it deleted.
+
+CREATE TABLE IF NOT EXISTS patient_allergies (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    patient_id UUID NOT NULL REFERENCES patients(id) ON DELETE CASCADE,
+    allergy_type VARCHAR(20) NOT NULL DEFAULT 'ALLERGY' CHECK (
+        allergy_type IN ('ALLERGY', 'INTOLERANCE')
+    ),
+    category VARCHAR(20) NOT NULL CHECK (
+        category IN ('DRUG', 'FOOD', 'ENVIRONMENTAL', 'OTHER')
+    ),
+    substance TEXT NOT NULL,           -- 🔒 ENCRYPT
+    reaction TEXT,                     -- 🔒 ENCRYPT
+    severity VARCHAR(20) CHECK (
+        severity IN ('MILD', 'MODERATE', 'SEVERE', 'LIFE_THREATENING')
+    ),
+    onset_date DATE,
+    status VARCHAR(20) NOT NULL DEFAULT 'ACTIVE' CHECK (
+        status IN ('ACTIVE', 'INACTIVE', 'RESOLVED', 'ENTERED_IN_ERROR')
+    ),
+    notes TEXT,                        -- 🔒 ENCRYPT
+
+    created_by UUID NOT NULL REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_by UUID REFERENCES users(id),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    deleted_at TIMESTAMPTZ,
+    deleted_by UUID REFERENCES users(id),
+
+    CONSTRAINT patient_allergies_deletion CHECK ((deleted_at IS NULL) = (deleted_by IS NULL))
+);
+
+CREATE INDEX IF NOT EXISTS idx_patient_allergies_patient
+    ON patient_allergies (patient_id, status)
+    WHERE deleted_at IS NULL;
+
+COMMENT ON TABLE patient_allergies IS 'Structured allergies and intolerances of a patient';
+COMMENT ON COLUMN patient_allergies.substance IS '🔒 ENCRYPTED - Allergen or substance not tolerated';
+COMMENT ON COLUMN patient_allergies.reaction IS '🔒 ENCRYPTED - Observed reaction';
+COMMENT ON COLUMN patient_allergies.notes IS '🔒 ENCRYPTED - Free-text notes';
+
+CREATE TRIGGER update_patient_allergies_updated_at
+    BEFORE UPDATE ON patient_allergies
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
+
+-- No new allergies for erased or merged patients
+CREATE TRIGGER trigger_prevent_anonymized_patient_allergy
+    BEFORE INSERT ON patient_allergies
+    FOR EACH ROW
+    EXECUTE FUNCTION prevent_anonymized_patient_reference();
+
+-- ====================
+-- RLS
+-- ====================
+
+ALTER TABLE patient_allergies ENABLE ROW LEVEL SECURITY;
+ALTER TABLE patient_allergies FORCE ROW LEVEL SECURITY;
+
+CREATE POLICY patient_allergies_select_policy ON patient_allergies
+    FOR SELECT
+    USING (is_doctor() OR is_nurse());
+
+-- Nurses record allergies at intake
+CREATE POLICY patient_allergies_insert_policy ON patient_allergies
+    FOR INSERT
+    WITH CHECK (is_doctor() OR is_nurse());
+
+-- Edits, soft deletes and patient merges (ADMIN)
+CREATE POLICY patient_allergies_update_policy ON patient_allergies
+    FOR UPDATE
+    USING (is_doctor())
+    WITH CHECK (is_doctor());
+
+GRANT SELECT, INSERT, UPDATE ON patient_allergies TO mpms_user;
+
+-- ====================
+-- CASBIN POLICIES
+-- ====================
+-- Same rules as casbin/policy.csv, for installs that already seeded the table
+
+INSERT INTO casbin_rules (ptype, v0, v1, v2)
+SELECT v.ptype, v.v0, v.v1, v.v2
+FROM (VALUES
+    ('p', 'ADMIN', 'allergies', 'create'),
+    ('p', 'ADMIN', 'allergies', 'read'),
+    ('p', 'ADMIN', 'allergies', 'update'),
+    ('p', 'ADMIN', 'allergies', 'delete'),
+    ('p', 'DOCTOR', 'allergies', 'create'),
+    ('p', 'DOCTOR', 'allergies', 'read'),
+    ('p', 'DOCTOR', 'allergies', 'update'),
+    ('p', 'DOCTOR', 'allergies', 'delete'),
+    ('p', 'NURSE', 'allergies', 'create'),
+    ('p', 'NURSE', 'allergies', 'read')
+) AS v (ptype, v0, v1, v2)
+WHERE EXISTS (SELECT 1 FROM casbin_rules)
+ON CONFLICT (ptype, v0, v1, v2, v3, v4, v5) DO NOTHING;
@@ -0,0 +1,131 @@
+-- Migration: Patient Problem List
+-- Date: 2026-03-30
+--
+-- Longitudinal problem list: the patient's ongoing conditions (ICD-10 coded,
+-- with onset and active/resolved status), kept across visits. Unlike visit
+-- diagnoses, which record what was assessed at one visit, a problem stays on
+-- the list until it is resolved, and links the visits where it was addressed.
+-- A visit diagnosis can be promoted onto the list; the link to it is kept in
+-- source_diagnosis_id. Clinical notes are encrypted.
+
+CREATE TABLE IF NOT EXISTS patient_problems (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    patient_id UUID NOT NULL REFERENCES patients(id) ON DELETE CASCADE,
+    icd10_code VARCHAR(10) NOT NULL,
+    icd10_description TEXT NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'ACTIVE' CHECK (status IN ('ACTIVE', 'RESOLVED')),
+    onset_date DATE,
+    resolved_date DATE,
+    clinical_notes TEXT,               -- 🔒 ENCRYPT
+    -- Visit diagnosis the problem was promoted from
+    source_diagnosis_id UUID REFERENCES visit_diagnoses(id) ON DELETE SET NULL,
+
+    created_by UUID NOT NULL REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_by UUID REFERENCES users(id),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    deleted_at TIMESTAMPTZ,
+    deleted_by UUID REFERENCES users(id),
+
+    CONSTRAINT patient_problems_deletion CHECK ((deleted_at IS NULL) = (deleted_by IS NULL)),
+    CONSTRAINT patient_problems_resolution CHECK (
+        resolved_date IS NULL OR onset_date IS NULL OR resolved_date >= onset_date
+    )
+);
+
+-- One active entry per condition
+CREATE UNIQUE INDEX IF NOT EXISTS idx_patient_problems_active_code
+    ON patient_problems (patient_id, icd10_code)
+    WHERE status = 'ACTIVE' AND deleted_at IS NULL;
+CREATE INDEX IF NOT EXISTS idx_patient_problems_patient
+    ON patient_problems (patient_id, status)
+    WHERE deleted_at IS NULL;
+
+COMMENT ON TABLE patient_problems IS 'Longitudinal problem list, distinct from per-visit diagnoses';
+COMMENT ON COLUMN patient_problems.clinical_notes IS '🔒 ENCRYPTED - Notes on the problem';
+
+CREATE TRIGGER update_patient_problems_updated_at
+    BEFORE UPDATE ON patient_problems
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
+
+-- No new problems for erased or merged patients
+CREATE TRIGGER trigger_prevent_anonymized_patient_problem
+    BEFORE INSERT ON patient_problems
+    FOR EACH ROW
+    EXECUTE FUNCTION prevent_anonymized_patient_reference();
+
+-- Visits where a problem was addressed
+CREATE TABLE IF NOT EXISTS patient_problem_visits (
+    problem_id UUID NOT NULL REFERENCES patient_problems(id) ON DELETE CASCADE,
+    visit_id UUID NOT NULL,
+    visit_date DATE NOT NULL,  -- Required for partition key in visits table
+    linked_by UUID NOT NULL REFERENCES users(id),
+    linked_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    PRIMARY KEY (problem_id, visit_id),
+    FOREIGN KEY (visit_id, visit_date) REFERENCES visits(id, visit_date) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_patient_problem_visits_visit
+    ON patient_problem_visits (visit_id);
+
+-- ====================
+-- RLS
+-- ====================
+
+ALTER TABLE patient_problems ENABLE ROW LEVEL SECURITY;
+ALTER TABLE patient_problems FORCE ROW LEVEL SECURITY;
+
+CREATE POLICY patient_problems_select_policy ON patient_problems
+    FOR SELECT
+    USING (is_doctor() OR is_nurse());
+
+-- Problems are clinical judgements: doctors only (ADMIN for patient merges)
+CREATE POLICY patient_problems_insert_policy ON patient_problems
+    FOR INSERT
+    WITH CHECK (is_doctor());
+
+CREATE POLICY patient_problems_update_policy ON patient_problems
+    FOR UPDATE
+    USING (is_doctor())
+    WITH CHECK (is_doctor());
+
+ALTER TABLE patient_problem_visits ENABLE ROW LEVEL SECURITY;
+ALTER TABLE patient_problem_visits FORCE ROW LEVEL SECURITY;
+
+CREATE POLICY patient_problem_visits_select_policy ON patient_problem_visits
+    FOR SELECT
+    USING (is_doctor() OR is_nurse());
+
+CREATE POLICY patient_problem_visits_insert_policy ON patient_problem_visits
+    FOR INSERT
+    WITH CHECK (is_doctor());
+
+CREATE POLICY patient_problem_visits_delete_policy ON patient_problem_visits
+    FOR DELETE
+    USING (is_doctor());
+
+GRANT SELECT, INSERT, UPDATE ON patient_problems TO mpms_user;
+GRANT SELECT, INSERT, DELETE ON patient_problem_visits TO mpms_user;
+
+-- ====================
+-- CASBIN POLICIES
+-- ====================
+-- Same rules as casbin/policy.csv, for installs that already seeded the table
+
+INSERT INTO casbin_rules (ptype, v0, v1, v2)
+SELECT v.ptype, v.v0, v.v1, v.v2
+FROM (VALUES
+    ('p', 'ADMIN', 'problems', 'create'),
+    ('p', 'ADMIN', 'problems', 'read'),
+    ('p', 'ADMIN', 'problems', 'update'),
+    ('p', 'ADMIN', 'problems', 'delete'),
+    ('p', 'DOCTOR', 'problems', 'create'),
+    ('p', 'DOCTOR', 'problems', 'read'),
+    ('p', 'DOCTOR', 'problems', 'update'),
+    ('p', 'DOCTOR', 'problems', 'delete'),
+    ('p', 'NURSE', 'problems', 'read')
+) AS v (ptype, v0, v1, v2)
+WHERE EXISTS (SELECT 1 FROM casbin_rules)
+ON CONFLICT (ptype, v0, v1, v2, v3, v4, v5) DO NOTHING;
@@ -0,0 +1,206 @@
+-- Migration: Lab Orders and Results
+-- Date: 2026-03-31
+--
+-- Laboratory orders (the tests requested for a patient, optionally at a
+-- visit) and their results, coded with LOINC. A result may belong to an
+-- order or be recorded on its own (e.g. from an external lab report).
+-- Result values, clinical information and notes are encrypted; the LOINC
+-- code, unit, reference range and abnormal flag stay in clear so results
+-- can be filtered and trended per analyte.
+
+CREATE TABLE IF NOT EXISTS lab_orders (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    patient_id UUID NOT NULL REFERENCES patients(id) ON DELETE CASCADE,
+    -- Visit the tests were ordered at
+    visit_id UUID,
+    visit_date DATE,  -- Required for partition key in visits table
+    status VARCHAR(20) NOT NULL DEFAULT 'ORDERED'
+        CHECK (status IN ('ORDERED', 'COMPLETED', 'CANCELLED')),
+    urgency VARCHAR(20) NOT NULL DEFAULT 'ROUTINE' CHECK (urgency IN ('ROUTINE', 'URGENT')),
+    fasting BOOLEAN NOT NULL DEFAULT false,
+    lab_name VARCHAR(255),
+    clinical_info TEXT,                -- 🔒 ENCRYPT
+    ordered_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    created_by UUID NOT NULL REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_by UUID REFERENCES users(id),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    deleted_at TIMESTAMPTZ,
+    deleted_by UUID REFERENCES users(id),
+
+    FOREIGN KEY (visit_id, visit_date) REFERENCES visits(id, visit_date) ON DELETE SET NULL,
+    CONSTRAINT lab_orders_visit CHECK ((visit_id IS NULL) = (visit_date IS NULL)),
+    CONSTRAINT lab_orders_deletion CHECK ((deleted_at IS NULL) = (deleted_by IS NULL))
+);
+
+CREATE INDEX IF NOT EXISTS idx_lab_orders_patient
+    ON lab_orders (patient_id, ordered_at DESC)
+    WHERE deleted_at IS NULL;
+CREATE INDEX IF NOT EXISTS idx_lab_orders_visit
+    ON lab_orders (visit_id)
+    WHERE visit_id IS NOT NULL;
+
+COMMENT ON TABLE lab_orders IS 'Laboratory test orders';
+COMMENT ON COLUMN lab_orders.clinical_info IS '🔒 ENCRYPTED - Clinical information for the lab';
+
+CREATE TRIGGER update_lab_orders_updated_at
+    BEFORE UPDATE ON lab_orders
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
+
+CREATE TRIGGER trigger_prevent_anonymized_patient_lab_order
+    BEFORE INSERT ON lab_orders
+    FOR EACH ROW
+    EXECUTE FUNCTION prevent_anonymized_patient_reference();
+
+-- Tests requested by an order
+CREATE TABLE IF NOT EXISTS lab_order_tests (
+    order_id UUID NOT NULL REFERENCES lab_orders(id) ON DELETE CASCADE,
+    loinc_code VARCHAR(10) NOT NULL,
+    test_name VARCHAR(255) NOT NULL,
+    position INTEGER NOT NULL DEFAULT 0,
+
+    PRIMARY KEY (order_id, loinc_code)
+);
+
+CREATE TABLE IF NOT EXISTS lab_results (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    patient_id UUID NOT NULL REFERENCES patients(id) ON DELETE CASCADE,
+    order_id UUID REFERENCES lab_orders(id) ON DELETE SET NULL,
+    visit_id UUID,
+    visit_date DATE,  -- Required for partition key in visits table
+    loinc_code VARCHAR(10) NOT NULL,
+    test_name VARCHAR(255) NOT NULL,
+    value TEXT NOT NULL,               -- 🔒 ENCRYPT - numeric or textual ("positive")
+    unit VARCHAR(50),
+    reference_low DOUBLE PRECISION,
+    reference_high DOUBLE PRECISION,
+    -- Free-text range for non-numeric analytes ("negative")
+    reference_text VARCHAR(100),
+    abnormal_flag VARCHAR(20) CHECK (abnormal_flag IN (
+        'NORMAL', 'LOW', 'HIGH', 'CRITICAL_LOW', 'CRITICAL_HIGH', 'ABNORMAL'
+    )),
+    collected_at TIMESTAMPTZ NOT NULL,
+    resulted_at TIMESTAMPTZ,
+    notes TEXT,                        -- 🔒 ENCRYPT
+
+    created_by UUID NOT NULL REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_by UUID REFERENCES users(id),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    deleted_at TIMESTAMPTZ,
+    deleted_by UUID REFERENCES users(id),
+
+    FOREIGN KEY (visit_id, visit_date) REFERENCES visits(id, visit_date) ON DELETE SET NULL,
+    CONSTRAINT lab_results_visit CHECK ((visit_id IS NULL) = (visit_date IS NULL)),
+    CONSTRAINT lab_results_range CHECK (
+        reference_low IS NULL OR reference_high IS NULL OR reference_low <= reference_high
+    ),
+    CONSTRAINT lab_results_deletion CHECK ((deleted_at IS NULL) = (deleted_by IS NULL))
+);
+
+-- Trend queries: one analyte of one patient over time
+CREATE INDEX IF NOT EXISTS idx_lab_results_patient_loinc
+    ON lab_results (patient_id, loinc_code, collected_at)
+    WHERE deleted_at IS NULL;
+CREATE INDEX IF NOT EXISTS idx_lab_results_order
+    ON lab_results (order_id)
+    WHERE order_id IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_lab_results_visit
+    ON lab_results (visit_id)
+    WHERE visit_id IS NOT NULL;
+
+COMMENT ON TABLE lab_results IS 'Laboratory results, LOINC coded';
+COMMENT ON COLUMN lab_results.value IS '🔒 ENCRYPTED - Result value';
+COMMENT ON COLUMN lab_results.notes IS '🔒 ENCRYPTED - Notes on the result';
+
+CREATE TRIGGER update_lab_results_updated_at
+    BEFORE UPDATE ON lab_results
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
+
+CREATE TRIGGER trigger_prevent_anonymized_patient_lab_result
+    BEFORE INSERT ON lab_results
+    FOR EACH ROW
+    EXECUTE FUNCTION prevent_anonymized_patient_reference();
+
+-- ====================
+-- RLS
+-- ====================
+
+ALTER TABLE lab_orders ENABLE ROW LEVEL SECURITY;
+ALTER TABLE lab_orders FORCE ROW LEVEL SECURITY;
+
+CREATE POLICY lab_orders_select_policy ON lab_orders
+    FOR SELECT
+    USING (is_doctor() OR is_nurse());
+
+CREATE POLICY lab_orders_insert_policy ON lab_orders
+    FOR INSERT
+    WITH CHECK (is_doctor());
+
+CREATE POLICY lab_orders_update_policy ON lab_orders
+    FOR UPDATE
+    USING (is_doctor())
+    WITH CHECK (is_doctor());
+
+ALTER TABLE lab_order_tests ENABLE ROW LEVEL SECURITY;
+ALTER TABLE lab_order_tests FORCE ROW LEVEL SECURITY;
+
+CREATE POLICY lab_order_tests_select_policy ON lab_order_tests
+    FOR SELECT
+    USING (is_doctor() OR is_nurse());
+
+CREATE POLICY lab_order_tests_insert_policy ON lab_order_tests
+    FOR INSERT
+    WITH CHECK (is_doctor());
+
+CREATE POLICY lab_order_tests_delete_policy ON lab_order_tests
+    FOR DELETE
+    USING (is_doctor());
+
+ALTER TABLE lab_results ENABLE ROW LEVEL SECURITY;
+ALTER TABLE lab_results FORCE ROW LEVEL SECURITY;
+
+-- Nurses record results as they come back from the lab
+CREATE POLICY lab_results_select_policy ON lab_results
+    FOR SELECT
+    USING (is_doctor() OR is_nurse());
+
+CREATE POLICY lab_results_insert_policy ON lab_results
+    FOR INSERT
+    WITH CHECK (is_doctor() OR is_nurse());
+
+CREATE POLICY lab_results_update_policy ON lab_results
+    FOR UPDATE
+    USING (is_doctor())
+    WITH CHECK (is_doctor());
+
+GRANT SELECT, INSERT, UPDATE ON lab_orders TO mpms_user;
+GRANT SELECT, INSERT, DELETE ON lab_order_tests TO mpms_user;
+GRANT SELECT, INSERT, UPDATE ON lab_results TO mpms_user;
+
+-- ====================
+-- CASBIN POLICIES
+-- ====================
+-- Same rules as casbin/policy.csv, for installs that already seeded the table
+
+INSERT INTO casbin_rules (ptype, v0, v1, v2)
+SELECT v.ptype, v.v0, v.v1, v.v2
+FROM (VALUES
+    ('p', 'ADMIN', 'labs', 'create'),
+    ('p', 'ADMIN', 'labs', 'read'),
+    ('p', 'ADMIN', 'labs', 'update'),
+    ('p', 'ADMIN', 'labs', 'delete'),
+    ('p', 'ADMIN', 'labs', 'record_result'),
+    ('p', 'DOCTOR', 'labs', 'create'),
+    ('p', 'DOCTOR', 'labs', 'read'),
+    ('p', 'DOCTOR', 'labs', 'update'),
+    ('p', 'DOCTOR', 'labs', 'delete'),
+    ('p', 'DOCTOR', 'labs', 'record_result'),
+    ('p', 'NURSE', 'labs', 'record_result'),
+    ('p', 'NURSE', 'labs', 'read')
+) AS v (ptype, v0, v1, v2)
+WHERE EXISTS (SELECT 1 FROM casbin_rules)
+ON CONFLICT (ptype, v0, v1, v2, v3, v4, v5) DO NOTHING;
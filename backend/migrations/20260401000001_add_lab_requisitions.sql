@@ -0,0 +1,162 @@
+-- Migration: Lab Requisitions
+-- Date: 2026-04-01
+--
+-- First-class lab-order workflow on top of lab_orders:
+-- - lab_test_catalog: the tests doctors pick from when placing an order
+-- - a requisition number per order, printed as a Code 39 barcode on the
+--   LAB_REQUEST document, and a link to the last generated requisition
+-- - PARTIAL status: some, but not all, requested tests have a result. The
+--   service moves ORDERED -> PARTIAL -> COMPLETED as results are recorded.
+
+-- ====================
+-- TEST CATALOG
+-- ====================
+
+CREATE TABLE IF NOT EXISTS lab_test_catalog (
+    loinc_code VARCHAR(10) PRIMARY KEY,
+    test_name VARCHAR(255) NOT NULL,
+    category VARCHAR(50) NOT NULL,
+    -- Blood, serum, plasma, urine...
+    specimen VARCHAR(50) NOT NULL,
+    fasting_required BOOLEAN NOT NULL DEFAULT false,
+    is_active BOOLEAN NOT NULL DEFAULT true,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_lab_test_catalog_category
+    ON lab_test_catalog (category, test_name)
+    WHERE is_active;
+
+COMMENT ON TABLE lab_test_catalog IS 'Orderable lab tests, LOINC coded';
+
+CREATE TRIGGER update_lab_test_catalog_updated_at
+    BEFORE UPDATE ON lab_test_catalog
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
+
+INSERT INTO lab_test_catalog (loinc_code, test_name, category, specimen, fasting_required) VALUES
+    ('58410-2', 'Emocromo completo', 'EMATOLOGIA', 'Sangue intero', false),
+    ('718-7', 'Emoglobina', 'EMATOLOGIA', 'Sangue intero', false),
+    ('4544-3', 'Ematocrito', 'EMATOLOGIA', 'Sangue intero', false),
+    ('6690-2', 'Leucociti', 'EMATOLOGIA', 'Sangue intero', false),
+    ('789-8', 'Eritrociti', 'EMATOLOGIA', 'Sangue intero', false),
+    ('777-3', 'Piastrine', 'EMATOLOGIA', 'Sangue intero', false),
+    ('4537-7', 'VES', 'EMATOLOGIA', 'Sangue intero', false),
+    ('2345-7', 'Glucosio', 'CHIMICA CLINICA', 'Siero', true),
+    ('4548-4', 'Emoglobina glicata (HbA1c)', 'CHIMICA CLINICA', 'Sangue intero', false),
+    ('2093-3', 'Colesterolo totale', 'CHIMICA CLINICA', 'Siero', true),
+    ('2085-9', 'Colesterolo HDL', 'CHIMICA CLINICA', 'Siero', true),
+    ('13457-7', 'Colesterolo LDL (calcolato)', 'CHIMICA CLINICA', 'Siero', true),
+    ('2571-8', 'Trigliceridi', 'CHIMICA CLINICA', 'Siero', true),
+    ('2160-0', 'Creatinina', 'CHIMICA CLINICA', 'Siero', false),
+    ('3094-0', 'Azotemia', 'CHIMICA CLINICA', 'Siero', false),
+    ('3084-1', 'Acido urico', 'CHIMICA CLINICA', 'Siero', false),
+    ('1742-6', 'ALT (GPT)', 'CHIMICA CLINICA', 'Siero', false),
+    ('1920-8', 'AST (GOT)', 'CHIMICA CLINICA', 'Siero', false),
+    ('2324-2', 'Gamma-GT', 'CHIMICA CLINICA', 'Siero', false),
+    ('1975-2', 'Bilirubina totale', 'CHIMICA CLINICA', 'Siero', false),
+    ('2885-2', 'Proteine totali', 'CHIMICA CLINICA', 'Siero', false),
+    ('1751-7', 'Albumina', 'CHIMICA CLINICA', 'Siero', false),
+    ('2951-2', 'Sodio', 'CHIMICA CLINICA', 'Siero', false),
+    ('2823-3', 'Potassio', 'CHIMICA CLINICA', 'Siero', false),
+    ('17861-6', 'Calcio', 'CHIMICA CLINICA', 'Siero', false),
+    ('2498-4', 'Sideremia', 'CHIMICA CLINICA', 'Siero', true),
+    ('2276-4', 'Ferritina', 'CHIMICA CLINICA', 'Siero', false),
+    ('1988-5', 'Proteina C reattiva', 'CHIMICA CLINICA', 'Siero', false),
+    ('2132-9', 'Vitamina B12', 'CHIMICA CLINICA', 'Siero', false),
+    ('2284-8', 'Folati', 'CHIMICA CLINICA', 'Siero', false),
+    ('1989-3', '25-OH Vitamina D', 'CHIMICA CLINICA', 'Siero', false),
+    ('3016-3', 'TSH', 'ENDOCRINOLOGIA', 'Siero', false),
+    ('3024-7', 'FT4', 'ENDOCRINOLOGIA', 'Siero', false),
+    ('2857-1', 'PSA totale', 'ENDOCRINOLOGIA', 'Siero', false),
+    ('5902-2', 'Tempo di protrombina (PT)', 'COAGULAZIONE', 'Plasma', false),
+    ('6301-6', 'INR', 'COAGULAZIONE', 'Plasma', false),
+    ('3173-2', 'aPTT', 'COAGULAZIONE', 'Plasma', false),
+    ('24356-8', 'Esame urine completo', 'URINE', 'Urine', false),
+    ('14959-1', 'Microalbuminuria', 'URINE', 'Urine', false)
+ON CONFLICT (loinc_code) DO NOTHING;
+
+-- Reference data: every authenticated role can read it, ADMIN maintains it
+ALTER TABLE lab_test_catalog ENABLE ROW LEVEL SECURITY;
+
+CREATE POLICY lab_test_catalog_select_policy ON lab_test_catalog
+    FOR SELECT
+    USING (true);
+
+CREATE POLICY lab_test_catalog_write_policy ON lab_test_catalog
+    FOR ALL
+    USING (is_admin())
+    WITH CHECK (is_admin());
+
+GRANT SELECT, INSERT, UPDATE ON lab_test_catalog TO mpms_user;
+
+-- ====================
+-- REQUISITIONS
+-- ====================
+
+CREATE SEQUENCE IF NOT EXISTS lab_requisition_number_seq;
+
+ALTER TABLE lab_orders
+    -- LAB-YYYY-NNNNNN, assigned the first time a requisition is generated
+    ADD COLUMN IF NOT EXISTS requisition_number VARCHAR(20) UNIQUE,
+    ADD COLUMN IF NOT EXISTS requisition_document_id UUID
+        REFERENCES generated_documents(id) ON DELETE SET NULL;
+
+COMMENT ON COLUMN lab_orders.requisition_number IS 'Number printed (and barcoded) on the requisition';
+COMMENT ON COLUMN lab_orders.requisition_document_id IS 'Most recently generated requisition PDF';
+
+GRANT USAGE, SELECT ON SEQUENCE lab_requisition_number_seq TO mpms_user;
+
+-- ====================
+-- STATUS
+-- ====================
+
+ALTER TABLE lab_orders DROP CONSTRAINT IF EXISTS lab_orders_status_check;
+ALTER TABLE lab_orders ADD CONSTRAINT lab_orders_status_check
+    CHECK (status IN ('ORDERED', 'PARTIAL', 'COMPLETED', 'CANCELLED'));
+
+-- Nurses record results, which moves the order forward; they can make no
+-- other change to an order (Casbin keeps them off the update endpoint)
+CREATE POLICY lab_orders_nurse_status_policy ON lab_orders
+    FOR UPDATE
+    USING (is_nurse() AND status IN ('ORDERED', 'PARTIAL'))
+    WITH CHECK (is_nurse() AND status IN ('ORDERED', 'PARTIAL', 'COMPLETED'));
+
+-- ====================
+-- REQUISITION TEMPLATE
+-- ====================
+-- Print the requisition number and its barcode under the title of the
+-- default Italian lab request
+
+UPDATE document_templates
+SET template_html = replace(
+        template_html,
+        '<h1 class="title">RICHIESTA ESAMI DI LABORATORIO</h1>',
+        E'<h1 class="title">RICHIESTA ESAMI DI LABORATORIO</h1>
+
+    {% if lab.requisition_number %}
+    <div class="requisition">
+        <img class="barcode" src="{{lab.barcode}}" alt="{{lab.requisition_number}}">
+        <p><strong>N. richiesta:</strong> {{lab.requisition_number}}</p>
+    </div>
+    {% endif %}'
+    ),
+    css_styles = COALESCE(css_styles, '') || E'\n.requisition { text-align: right; margin-bottom: 15px; }\n.barcode { height: 50px; }',
+    updated_at = NOW()
+WHERE template_key = 'lab_request_it'
+  AND template_html NOT LIKE '%lab.requisition_number%';
+
+-- ====================
+-- CASBIN POLICIES
+-- ====================
+-- Same rules as casbin/policy.csv, for installs that already seeded the table
+
+INSERT INTO casbin_rules (ptype, v0, v1, v2)
+SELECT v.ptype, v.v0, v.v1, v.v2
+FROM (VALUES
+    ('p', 'ADMIN', 'labs', 'requisition'),
+    ('p', 'DOCTOR', 'labs', 'requisition')
+) AS v (ptype, v0, v1, v2)
+WHERE EXISTS (SELECT 1 FROM casbin_rules)
+ON CONFLICT (ptype, v0, v1, v2, v3, v4, v5) DO NOTHING;
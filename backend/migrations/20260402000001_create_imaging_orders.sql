@@ -0,0 +1,222 @@
+-- Migration: Imaging Orders
+-- Date: 2026-04-02
+--
+-- Diagnostic imaging requested for a patient (X-ray, CT, MRI, ultrasound...),
+-- optionally at a visit, tracked from ORDERED to PERFORMED to REPORTED. The
+-- clinical question and the radiologist's report are encrypted; modality,
+-- body part and urgency stay in clear for filtering.
+--
+-- An order can be printed as a referral letter (REFERRAL_LETTER document,
+-- template imaging_referral_it), linked back through referral_document_id.
+
+CREATE TABLE IF NOT EXISTS imaging_orders (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    patient_id UUID NOT NULL REFERENCES patients(id) ON DELETE CASCADE,
+    -- Visit the imaging was ordered at
+    visit_id UUID,
+    visit_date DATE,  -- Required for partition key in visits table
+    modality VARCHAR(20) NOT NULL CHECK (modality IN (
+        'XRAY', 'CT', 'MRI', 'ULTRASOUND', 'MAMMOGRAPHY', 'DEXA', 'PET', 'NUCLEAR_MEDICINE', 'OTHER'
+    )),
+    body_part VARCHAR(100) NOT NULL,
+    laterality VARCHAR(20) CHECK (laterality IN ('LEFT', 'RIGHT', 'BILATERAL')),
+    contrast BOOLEAN NOT NULL DEFAULT false,
+    clinical_question TEXT NOT NULL,   -- 🔒 ENCRYPT
+    urgency VARCHAR(20) NOT NULL DEFAULT 'ROUTINE' CHECK (urgency IN ('ROUTINE', 'URGENT')),
+    status VARCHAR(20) NOT NULL DEFAULT 'ORDERED'
+        CHECK (status IN ('ORDERED', 'PERFORMED', 'REPORTED', 'CANCELLED')),
+    facility VARCHAR(255),
+    performed_at TIMESTAMPTZ,
+    reported_at TIMESTAMPTZ,
+    report TEXT,                       -- 🔒 ENCRYPT
+    referral_document_id UUID REFERENCES generated_documents(id) ON DELETE SET NULL,
+    ordered_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    created_by UUID NOT NULL REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_by UUID REFERENCES users(id),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    deleted_at TIMESTAMPTZ,
+    deleted_by UUID REFERENCES users(id),
+
+    FOREIGN KEY (visit_id, visit_date) REFERENCES visits(id, visit_date) ON DELETE SET NULL,
+    CONSTRAINT imaging_orders_visit CHECK ((visit_id IS NULL) = (visit_date IS NULL)),
+    CONSTRAINT imaging_orders_performed CHECK (
+        status NOT IN ('PERFORMED', 'REPORTED') OR performed_at IS NOT NULL
+    ),
+    CONSTRAINT imaging_orders_reported CHECK (
+        status <> 'REPORTED' OR reported_at IS NOT NULL
+    ),
+    CONSTRAINT imaging_orders_deletion CHECK ((deleted_at IS NULL) = (deleted_by IS NULL))
+);
+
+CREATE INDEX IF NOT EXISTS idx_imaging_orders_patient
+    ON imaging_orders (patient_id, ordered_at DESC)
+    WHERE deleted_at IS NULL;
+CREATE INDEX IF NOT EXISTS idx_imaging_orders_visit
+    ON imaging_orders (visit_id)
+    WHERE visit_id IS NOT NULL;
+-- Worklist of orders still waiting to be performed or reported
+CREATE INDEX IF NOT EXISTS idx_imaging_orders_open
+    ON imaging_orders (status, ordered_at)
+    WHERE deleted_at IS NULL AND status IN ('ORDERED', 'PERFORMED');
+
+COMMENT ON TABLE imaging_orders IS 'Diagnostic imaging orders';
+COMMENT ON COLUMN imaging_orders.clinical_question IS '🔒 ENCRYPTED - Clinical question for the radiologist';
+COMMENT ON COLUMN imaging_orders.report IS '🔒 ENCRYPTED - Radiology report';
+
+CREATE TRIGGER update_imaging_orders_updated_at
+    BEFORE UPDATE ON imaging_orders
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
+
+CREATE TRIGGER trigger_prevent_anonymized_patient_imaging_order
+    BEFORE INSERT ON imaging_orders
+    FOR EACH ROW
+    EXECUTE FUNCTION prevent_anonymized_patient_reference();
+
+-- ====================
+-- RLS
+-- ====================
+
+ALTER TABLE imaging_orders ENABLE ROW LEVEL SECURITY;
+ALTER TABLE imaging_orders FORCE ROW LEVEL SECURITY;
+
+CREATE POLICY imaging_orders_select_policy ON imaging_orders
+    FOR SELECT
+    USING (is_doctor() OR is_nurse());
+
+CREATE POLICY imaging_orders_insert_policy ON imaging_orders
+    FOR INSERT
+    WITH CHECK (is_doctor());
+
+CREATE POLICY imaging_orders_update_policy ON imaging_orders
+    FOR UPDATE
+    USING (is_doctor())
+    WITH CHECK (is_doctor());
+
+GRANT SELECT, INSERT, UPDATE ON imaging_orders TO mpms_user;
+
+-- ====================
+-- REFERRAL LETTER TEMPLATE
+-- ====================
+-- Not the default REFERRAL_LETTER template: the imaging endpoint picks it by
+-- key. It also fills the generic referral.* variables, so any referral
+-- template can be used instead.
+
+ALTER TABLE document_templates DISABLE ROW LEVEL SECURITY;
+
+INSERT INTO document_templates (
+    template_key, template_name, description, document_type,
+    template_html, template_variables, header_html, footer_html, css_styles,
+    page_size, page_orientation, margin_top_mm, margin_bottom_mm, margin_left_mm, margin_right_mm,
+    is_active, is_default, language
+) VALUES (
+    'imaging_referral_it',
+    'Richiesta Esame Diagnostico per Immagini',
+    'Impegnativa per esami di diagnostica per immagini generata da una richiesta di imaging',
+    'REFERRAL_LETTER',
+    E'<div class="imaging-referral">
+    <h1 class="title">RICHIESTA DI ESAME DIAGNOSTICO PER IMMAGINI</h1>
+
+    <table class="info-table">
+        <tr>
+            <td><strong>Paziente:</strong></td>
+            <td>{{patient.full_name}}</td>
+            <td><strong>Data di nascita:</strong></td>
+            <td>{{patient.date_of_birth}}</td>
+        </tr>
+        <tr>
+            <td><strong>Codice Fiscale:</strong></td>
+            <td>{{patient.fiscal_code}}</td>
+            <td><strong>Priorità:</strong></td>
+            <td>{{imaging.urgency}}</td>
+        </tr>
+    </table>
+
+    <div class="exam">
+        <h3>Esame Richiesto</h3>
+        <p><strong>{{imaging.modality}}</strong> - {{imaging.body_part}}{% if imaging.laterality %} ({{imaging.laterality}}){% endif %}</p>
+        {% if imaging.contrast %}
+        <p class="warning"><strong>Con mezzo di contrasto</strong></p>
+        {% endif %}
+        {% if imaging.facility %}
+        <p><strong>Struttura:</strong> {{imaging.facility}}</p>
+        {% endif %}
+    </div>
+
+    <div class="clinical-info">
+        <h3>Quesito Clinico</h3>
+        <p>{{imaging.clinical_question}}</p>
+    </div>
+
+    {% if patient.allergies %}
+    <div class="allergies">
+        <h3>Allergie</h3>
+        <ul>
+        {% for allergy in patient.allergies %}
+            <li>{{allergy.substance}}{% if allergy.reaction %} - {{allergy.reaction}}{% endif %}</li>
+        {% endfor %}
+        </ul>
+    </div>
+    {% endif %}
+
+    <div class="footer-section">
+        <div class="date-location">
+            <p>{{clinic.city}}, {{document.date}}</p>
+        </div>
+        <div class="signature">
+            <p>Il Medico Richiedente</p>
+            <p class="signature-line">_________________________</p>
+            <p>Dr. {{provider.full_name}}</p>
+        </div>
+    </div>
+</div>',
+    '{"required": ["patient", "provider", "clinic", "imaging", "document"], "patient": ["full_name", "date_of_birth", "fiscal_code"], "provider": ["full_name"], "clinic": ["city"], "imaging": ["modality", "body_part", "clinical_question"], "document": ["date"]}',
+    E'<div class="header">
+    <div class="clinic-info">
+        <h2>{{clinic.name}}</h2>
+        <p>{{clinic.address}} - {{clinic.city}} ({{clinic.province}})</p>
+        <p>Tel: {{clinic.phone}}</p>
+    </div>
+</div>',
+    E'<div class="footer">
+    <p class="page-number">Pagina {{page_number}} di {{total_pages}}</p>
+</div>',
+    E'.imaging-referral { font-family: Arial, sans-serif; line-height: 1.5; }
+.title { text-align: center; margin-bottom: 20px; background-color: #f0f0f0; padding: 10px; }
+.info-table { width: 100%; border-collapse: collapse; }
+.info-table td { padding: 5px; }
+.exam, .clinical-info, .allergies { margin: 15px 0; }
+.warning { color: #c00; }
+.footer-section { margin-top: 40px; display: flex; justify-content: space-between; }
+.signature { text-align: center; }
+.signature-line { margin-top: 40px; }',
+    'A4', 'PORTRAIT', 20, 20, 20, 20,
+    true, false, 'it'
+) ON CONFLICT (template_key) DO NOTHING;
+
+ALTER TABLE document_templates ENABLE ROW LEVEL SECURITY;
+
+-- ====================
+-- CASBIN POLICIES
+-- ====================
+-- Same rules as casbin/policy.csv, for installs that already seeded the table
+
+INSERT INTO casbin_rules (ptype, v0, v1, v2)
+SELECT v.ptype, v.v0, v.v1, v.v2
+FROM (VALUES
+    ('p', 'ADMIN', 'imaging', 'create'),
+    ('p', 'ADMIN', 'imaging', 'read'),
+    ('p', 'ADMIN', 'imaging', 'update'),
+    ('p', 'ADMIN', 'imaging', 'delete'),
+    ('p', 'ADMIN', 'imaging', 'referral'),
+    ('p', 'DOCTOR', 'imaging', 'create'),
+    ('p', 'DOCTOR', 'imaging', 'read'),
+    ('p', 'DOCTOR', 'imaging', 'update'),
+    ('p', 'DOCTOR', 'imaging', 'delete'),
+    ('p', 'DOCTOR', 'imaging', 'referral'),
+    ('p', 'NURSE', 'imaging', 'read')
+) AS v (ptype, v0, v1, v2)
+WHERE EXISTS (SELECT 1 FROM casbin_rules)
+ON CONFLICT (ptype, v0, v1, v2, v3, v4, v5) DO NOTHING;
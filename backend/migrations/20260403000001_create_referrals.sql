@@ -0,0 +1,160 @@
+-- Migration: Referrals
+-- Date: 2026-04-03
+--
+-- Outgoing specialist referrals, tracked until the specialist's response
+-- comes back: PENDING (written) -> SENT (handed to the patient or the
+-- specialist) -> RESPONDED, or CANCELLED before a response.
+--
+-- A SENT referral whose response_due_date has passed is overdue. The
+-- notification scheduler emails the referring doctor about overdue
+-- referrals (REFERRAL_REMINDER), at most once every
+-- referral_reminder_interval_days.
+--
+-- The reason, clinical information and response summary are encrypted.
+-- The referral letter itself is a REFERRAL_LETTER document, linked back
+-- through referral_document_id.
+
+CREATE TABLE IF NOT EXISTS referrals (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    patient_id UUID NOT NULL REFERENCES patients(id) ON DELETE CASCADE,
+    -- Visit the referral was decided at
+    visit_id UUID,
+    visit_date DATE,  -- Required for partition key in visits table
+    specialty VARCHAR(100) NOT NULL,
+    specialist_name VARCHAR(255),
+    specialist_contact VARCHAR(255),
+    reason TEXT NOT NULL,              -- 🔒 ENCRYPT
+    clinical_info TEXT,                -- 🔒 ENCRYPT
+    urgency VARCHAR(20) NOT NULL DEFAULT 'ROUTINE' CHECK (urgency IN ('ROUTINE', 'URGENT')),
+    status VARCHAR(20) NOT NULL DEFAULT 'PENDING'
+        CHECK (status IN ('PENDING', 'SENT', 'RESPONDED', 'CANCELLED')),
+    sent_at TIMESTAMPTZ,
+    response_due_date DATE NOT NULL,
+    response_received_at TIMESTAMPTZ,
+    response_summary TEXT,             -- 🔒 ENCRYPT
+    -- Specialist report, uploaded as a patient attachment
+    response_attachment_id UUID REFERENCES patient_attachments(id) ON DELETE SET NULL,
+    referral_document_id UUID REFERENCES generated_documents(id) ON DELETE SET NULL,
+    last_reminder_at TIMESTAMPTZ,
+    reminder_count INTEGER NOT NULL DEFAULT 0,
+
+    created_by UUID NOT NULL REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_by UUID REFERENCES users(id),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    deleted_at TIMESTAMPTZ,
+    deleted_by UUID REFERENCES users(id),
+
+    FOREIGN KEY (visit_id, visit_date) REFERENCES visits(id, visit_date) ON DELETE SET NULL,
+    CONSTRAINT referrals_visit CHECK ((visit_id IS NULL) = (visit_date IS NULL)),
+    CONSTRAINT referrals_sent CHECK (
+        status NOT IN ('SENT', 'RESPONDED') OR sent_at IS NOT NULL
+    ),
+    CONSTRAINT referrals_responded CHECK (
+        status <> 'RESPONDED' OR response_received_at IS NOT NULL
+    ),
+    CONSTRAINT referrals_deletion CHECK ((deleted_at IS NULL) = (deleted_by IS NULL))
+);
+
+CREATE INDEX IF NOT EXISTS idx_referrals_patient
+    ON referrals (patient_id, created_at DESC)
+    WHERE deleted_at IS NULL;
+CREATE INDEX IF NOT EXISTS idx_referrals_visit
+    ON referrals (visit_id)
+    WHERE visit_id IS NOT NULL;
+-- Referrals waiting for a response, by due date (overdue list and reminders)
+CREATE INDEX IF NOT EXISTS idx_referrals_awaiting
+    ON referrals (response_due_date)
+    WHERE deleted_at IS NULL AND status = 'SENT';
+
+COMMENT ON TABLE referrals IS 'Outgoing specialist referrals and their responses';
+COMMENT ON COLUMN referrals.reason IS '🔒 ENCRYPTED - Reason for the referral';
+COMMENT ON COLUMN referrals.clinical_info IS '🔒 ENCRYPTED - Clinical information for the specialist';
+COMMENT ON COLUMN referrals.response_summary IS '🔒 ENCRYPTED - Summary of the specialist response';
+COMMENT ON COLUMN referrals.response_due_date IS 'Response expected by; overdue once past while SENT';
+
+CREATE TRIGGER update_referrals_updated_at
+    BEFORE UPDATE ON referrals
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
+
+CREATE TRIGGER trigger_prevent_anonymized_patient_referral
+    BEFORE INSERT ON referrals
+    FOR EACH ROW
+    EXECUTE FUNCTION prevent_anonymized_patient_reference();
+
+-- ====================
+-- RLS
+-- ====================
+
+ALTER TABLE referrals ENABLE ROW LEVEL SECURITY;
+ALTER TABLE referrals FORCE ROW LEVEL SECURITY;
+
+CREATE POLICY referrals_select_policy ON referrals
+    FOR SELECT
+    USING (is_doctor() OR is_nurse());
+
+CREATE POLICY referrals_insert_policy ON referrals
+    FOR INSERT
+    WITH CHECK (is_doctor());
+
+CREATE POLICY referrals_update_policy ON referrals
+    FOR UPDATE
+    USING (is_doctor())
+    WITH CHECK (is_doctor());
+
+GRANT SELECT, INSERT, UPDATE ON referrals TO mpms_user;
+
+-- ====================
+-- REMINDERS
+-- ====================
+
+ALTER TABLE notification_queue
+    DROP CONSTRAINT IF EXISTS notification_queue_notification_type_check;
+ALTER TABLE notification_queue
+    ADD CONSTRAINT notification_queue_notification_type_check CHECK (
+        notification_type IN ('APPOINTMENT_REMINDER', 'APPOINTMENT_BOOKED', 'APPOINTMENT_CONFIRMATION',
+                              'APPOINTMENT_CANCELLATION', 'VISIT_SUMMARY', 'PRESCRIPTION_READY',
+                              'FOLLOW_UP_REMINDER', 'CUSTOM', 'MARKETING', 'REFERRAL_REMINDER')
+    );
+
+INSERT INTO system_settings (
+    setting_key, setting_group, setting_name, setting_value,
+    value_type, description, default_value, is_public, is_encrypted, is_readonly
+) VALUES
+(
+    'referral_reminder_interval_days',
+    'notification',
+    'Referral Reminder Interval',
+    '7',
+    'INTEGER',
+    'Days between reminders to the referring doctor about a referral still waiting for the specialist response after its due date.',
+    '7',
+    false,
+    false,
+    false
+)
+ON CONFLICT (setting_key) DO NOTHING;
+
+-- ====================
+-- CASBIN POLICIES
+-- ====================
+-- Same rules as casbin/policy.csv, for installs that already seeded the table
+
+INSERT INTO casbin_rules (ptype, v0, v1, v2)
+SELECT v.ptype, v.v0, v.v1, v.v2
+FROM (VALUES
+    ('p', 'ADMIN', 'referrals', 'create'),
+    ('p', 'ADMIN', 'referrals', 'read'),
+    ('p', 'ADMIN', 'referrals', 'update'),
+    ('p', 'ADMIN', 'referrals', 'delete'),
+    ('p', 'ADMIN', 'referrals', 'letter'),
+    ('p', 'DOCTOR', 'referrals', 'create'),
+    ('p', 'DOCTOR', 'referrals', 'read'),
+    ('p', 'DOCTOR', 'referrals', 'update'),
+    ('p', 'DOCTOR', 'referrals', 'delete'),
+    ('p', 'DOCTOR', 'referrals', 'letter'),
+    ('p', 'NURSE', 'referrals', 'read')
+) AS v (ptype, v0, v1, v2)
+WHERE EXISTS (SELECT 1 FROM casbin_rules)
+ON CONFLICT (ptype, v0, v1, v2, v3, v4, v5) DO NOTHING;
@@ -0,0 +1,89 @@
+-- Migration: Visit calculations
+-- Date: 2026-04-04
+--
+-- Clinical calculator results (BMI, eGFR, CHA2DS2-VASc, HAS-BLED, SCORE2)
+-- saved into a visit. Only DRAFT visits take new results; a saved result
+-- can be deleted while the visit is still a draft.
+--
+-- The inputs and the result are clinical data and are stored encrypted
+-- (JSON).
+
+CREATE TABLE IF NOT EXISTS visit_calculations (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    visit_id UUID NOT NULL,
+    visit_date DATE NOT NULL,  -- Required for partition key in visits table
+    patient_id UUID NOT NULL REFERENCES patients(id) ON DELETE CASCADE,
+    calculator VARCHAR(20) NOT NULL
+        CHECK (calculator IN ('BMI', 'EGFR', 'CHA2DS2_VASC', 'HAS_BLED', 'SCORE2')),
+    inputs TEXT NOT NULL,              -- 🔒 ENCRYPT (JSON)
+    result TEXT NOT NULL,              -- 🔒 ENCRYPT (JSON)
+
+    created_by UUID NOT NULL REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    deleted_at TIMESTAMPTZ,
+    deleted_by UUID REFERENCES users(id),
+
+    FOREIGN KEY (visit_id, visit_date) REFERENCES visits(id, visit_date) ON DELETE CASCADE,
+    CONSTRAINT visit_calculations_deletion CHECK ((deleted_at IS NULL) = (deleted_by IS NULL))
+);
+
+CREATE INDEX IF NOT EXISTS idx_visit_calculations_visit
+    ON visit_calculations (visit_id, created_at)
+    WHERE deleted_at IS NULL;
+CREATE INDEX IF NOT EXISTS idx_visit_calculations_patient
+    ON visit_calculations (patient_id, calculator, created_at DESC)
+    WHERE deleted_at IS NULL;
+
+COMMENT ON TABLE visit_calculations IS 'Clinical calculator results saved into a visit';
+COMMENT ON COLUMN visit_calculations.inputs IS '🔒 ENCRYPTED - Calculator inputs (JSON)';
+COMMENT ON COLUMN visit_calculations.result IS '🔒 ENCRYPTED - Score, category and interpretation (JSON)';
+
+CREATE TRIGGER trigger_prevent_anonymized_patient_visit_calculation
+    BEFORE INSERT ON visit_calculations
+    FOR EACH ROW
+    EXECUTE FUNCTION prevent_anonymized_patient_reference();
+
+-- ====================
+-- RLS
+-- ====================
+
+ALTER TABLE visit_calculations ENABLE ROW LEVEL SECURITY;
+ALTER TABLE visit_calculations FORCE ROW LEVEL SECURITY;
+
+CREATE POLICY visit_calculations_select_policy ON visit_calculations
+    FOR SELECT
+    USING (is_doctor() OR is_nurse());
+
+CREATE POLICY visit_calculations_insert_policy ON visit_calculations
+    FOR INSERT
+    WITH CHECK (is_doctor() OR is_nurse());
+
+CREATE POLICY visit_calculations_update_policy ON visit_calculations
+    FOR UPDATE
+    USING (is_doctor())
+    WITH CHECK (is_doctor());
+
+GRANT SELECT, INSERT, UPDATE ON visit_calculations TO mpms_user;
+
+-- ====================
+-- CASBIN POLICIES
+-- ====================
+-- Same rules as casbin/policy.csv, for installs that already seeded the table
+
+INSERT INTO casbin_rules (ptype, v0, v1, v2)
+SELECT v.ptype, v.v0, v.v1, v.v2
+FROM (VALUES
+    ('p', 'ADMIN', 'calculators', 'calculate'),
+    ('p', 'ADMIN', 'calculators', 'read'),
+    ('p', 'ADMIN', 'calculators', 'save'),
+    ('p', 'ADMIN', 'calculators', 'delete'),
+    ('p', 'DOCTOR', 'calculators', 'calculate'),
+    ('p', 'DOCTOR', 'calculators', 'read'),
+    ('p', 'DOCTOR', 'calculators', 'save'),
+    ('p', 'DOCTOR', 'calculators', 'delete'),
+    ('p', 'NURSE', 'calculators', 'calculate'),
+    ('p', 'NURSE', 'calculators', 'read'),
+    ('p', 'NURSE', 'calculators', 'save')
+) AS v (ptype, v0, v1, v2)
+WHERE EXISTS (SELECT 1 FROM casbin_rules)
+ON CONFLICT (ptype, v0, v1, v2, v3, v4, v5) DO NOTHING;
@@ -0,0 +1,57 @@
+-- Migration: Prescription allergy overrides
+-- Date: 2026-04-05
+--
+-- New prescriptions are checked against the patient's active allergies
+-- (same substance, same drug class, or a cross-reactive class). A matching
+-- drug can only be prescribed with an override reason; one row per
+-- overridden alert is kept with the prescription.
+--
+-- The alert names the allergen and the reason is free text, so both are
+-- stored encrypted.
+
+CREATE TABLE IF NOT EXISTS prescription_allergy_overrides (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    prescription_id UUID NOT NULL REFERENCES prescriptions(id) ON DELETE CASCADE,
+    patient_id UUID NOT NULL REFERENCES patients(id) ON DELETE CASCADE,
+    allergy_id UUID NOT NULL REFERENCES patient_allergies(id),
+    match_type VARCHAR(20) NOT NULL
+        CHECK (match_type IN ('SUBSTANCE', 'CLASS', 'CROSS_REACTIVITY')),
+    alert TEXT NOT NULL,               -- 🔒 ENCRYPT (JSON)
+    reason TEXT NOT NULL,              -- 🔒 ENCRYPT
+
+    overridden_by UUID NOT NULL REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_prescription_allergy_overrides_prescription
+    ON prescription_allergy_overrides (prescription_id);
+CREATE INDEX IF NOT EXISTS idx_prescription_allergy_overrides_patient
+    ON prescription_allergy_overrides (patient_id, created_at DESC);
+
+COMMENT ON TABLE prescription_allergy_overrides IS 'Allergy alerts overridden to create a prescription';
+COMMENT ON COLUMN prescription_allergy_overrides.alert IS '🔒 ENCRYPTED - Matched allergy and drug class (JSON)';
+COMMENT ON COLUMN prescription_allergy_overrides.reason IS '🔒 ENCRYPTED - Prescriber''s reason for the override';
+
+-- ====================
+-- RLS
+-- ====================
+-- Only prescribers write overrides; nurses can read them like prescriptions
+
+ALTER TABLE prescription_allergy_overrides ENABLE ROW LEVEL SECURITY;
+ALTER TABLE prescription_allergy_overrides FORCE ROW LEVEL SECURITY;
+
+CREATE POLICY prescription_allergy_overrides_select_policy ON prescription_allergy_overrides
+    FOR SELECT
+    USING (is_doctor() OR is_nurse());
+
+CREATE POLICY prescription_allergy_overrides_insert_policy ON prescription_allergy_overrides
+    FOR INSERT
+    WITH CHECK (is_doctor());
+
+-- Patient merges move the overrides with the prescriptions
+CREATE POLICY prescription_allergy_overrides_update_policy ON prescription_allergy_overrides
+    FOR UPDATE
+    USING (is_doctor())
+    WITH CHECK (is_doctor());
+
+GRANT SELECT, INSERT, UPDATE ON prescription_allergy_overrides TO mpms_user;
@@ -0,0 +1,105 @@
+-- Migration: AIFA medication sync
+-- Date: 2026-04-06
+--
+-- The AIFA Class A list is imported into `medications` by
+-- services/medication_import_service.rs, from the admin endpoint, the
+-- import-medications CLI or a scheduled re-sync. One medication row is kept
+-- per commercial name; its AIFA packages (AIC code, package description,
+-- public price, equivalence group) are stored in `packages`.
+--
+-- Every sync, dry runs included, is recorded in medication_sync_runs with a
+-- diff of added, changed and withdrawn medications. Medications that left
+-- the list are deactivated, not deleted: prescriptions keep their names.
+
+-- ====================
+-- MEDICATIONS
+-- ====================
+
+ALTER TABLE medications
+    ADD COLUMN IF NOT EXISTS packages JSONB NOT NULL DEFAULT '[]'::jsonb,
+    ADD COLUMN IF NOT EXISTS public_price NUMERIC(10, 2);
+
+COMMENT ON COLUMN medications.packages IS 'AIFA packages: [{aic_code, description, price, equivalence_group}]';
+COMMENT ON COLUMN medications.public_price IS 'Lowest public price among the AIFA packages (EUR)';
+
+CREATE INDEX IF NOT EXISTS idx_medications_aifa_name
+    ON medications (name)
+    WHERE source = 'AIFA' AND is_custom = false;
+
+-- ====================
+-- SYNC RUNS
+-- ====================
+
+CREATE TABLE IF NOT EXISTS medication_sync_runs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    source VARCHAR(20) NOT NULL DEFAULT 'AIFA' CHECK (source IN ('AIFA')),
+    -- Only report what would change
+    dry_run BOOLEAN NOT NULL,
+    status VARCHAR(20) NOT NULL CHECK (status IN ('COMPLETED', 'FAILED')),
+    records_parsed INTEGER NOT NULL DEFAULT 0,
+    atc_codes_imported INTEGER NOT NULL DEFAULT 0,
+    added_count INTEGER NOT NULL DEFAULT 0,
+    updated_count INTEGER NOT NULL DEFAULT 0,
+    unchanged_count INTEGER NOT NULL DEFAULT 0,
+    deactivated_count INTEGER NOT NULL DEFAULT 0,
+    -- Added, changed and deactivated medications (capped per list)
+    diff JSONB NOT NULL DEFAULT '{}'::jsonb,
+    error_message TEXT,
+    -- NULL when run by the scheduler or the CLI
+    triggered_by UUID REFERENCES users(id),
+    started_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_medication_sync_runs_started
+    ON medication_sync_runs (started_at DESC);
+
+COMMENT ON TABLE medication_sync_runs IS 'History of AIFA medication imports with their diff, including dry runs';
+
+GRANT SELECT, INSERT ON medication_sync_runs TO mpms_user;
+
+-- ====================
+-- SETTINGS
+-- ====================
+
+INSERT INTO system_settings (
+    setting_key, setting_group, setting_name, setting_value,
+    value_type, description, default_value, is_public, is_encrypted, is_readonly
+) VALUES
+(
+    'medication_sync_interval_days',
+    'system',
+    'Medication Sync Interval (days)',
+    '30',
+    'INTEGER',
+    'Days between scheduled re-syncs of the AIFA medication list. 0 disables the scheduled sync.',
+    '30',
+    false,
+    false,
+    false
+),
+(
+    'aifa_class_a_url',
+    'system',
+    'AIFA Class A List URL',
+    '"https://www.aifa.gov.it/documents/20142/3174333/Classe_A_per_principio_attivo_30-06-2025.csv"',
+    'STRING',
+    'CSV of the AIFA Class A medications (semicolon-separated) downloaded by the medication sync.',
+    '"https://www.aifa.gov.it/documents/20142/3174333/Classe_A_per_principio_attivo_30-06-2025.csv"',
+    false,
+    false,
+    false
+),
+(
+    'aifa_atc_url',
+    'system',
+    'AIFA ATC Codes URL',
+    '""',
+    'STRING',
+    'Optional CSV of ATC codes (codice_atc;descrizione) imported before the medications. Empty to skip.',
+    '""',
+    false,
+    false,
+    false
+)
+ON CONFLICT (setting_key) DO NOTHING;
@@ -0,0 +1,224 @@
+-- Migration: Dematerialized prescriptions (NRE)
+-- Date: 2026-04-07
+--
+-- Prescriptions can be submitted to the Sistema TS dematerialized
+-- prescription service. The returned NRE (Numero Ricetta Elettronica) is
+-- stored in prescriptions.e_prescription_id and its status in
+-- e_prescription_status, updated by status callbacks as the pharmacy takes
+-- the prescription in charge and dispenses it.
+--
+-- e_prescription_events keeps every submission, callback and cancellation.
+-- The authentication code returned with the NRE (needed to cancel it) is
+-- kept encrypted on the ISSUED event.
+--
+-- The promemoria handed to the patient is a PRESCRIPTION document (template
+-- nre_promemoria_it) with the NRE and fiscal code barcodes.
+
+-- ====================
+-- PRESCRIPTIONS
+-- ====================
+
+ALTER TABLE prescriptions DROP CONSTRAINT IF EXISTS prescriptions_e_prescription_status_check;
+ALTER TABLE prescriptions ADD CONSTRAINT prescriptions_e_prescription_status_check
+    CHECK (e_prescription_status IS NULL OR e_prescription_status IN (
+        'ISSUED', 'REJECTED', 'IN_CHARGE', 'PARTIALLY_DISPENSED', 'DISPENSED', 'CANCELLED', 'EXPIRED'
+    ));
+
+-- Callbacks find the prescription by NRE
+CREATE UNIQUE INDEX IF NOT EXISTS idx_prescriptions_e_prescription_id
+    ON prescriptions (e_prescription_id)
+    WHERE e_prescription_id IS NOT NULL;
+
+COMMENT ON COLUMN prescriptions.e_prescription_id IS 'NRE assigned by Sistema TS';
+COMMENT ON COLUMN prescriptions.e_prescription_status IS 'Sistema TS status of the NRE, updated by status callbacks';
+
+-- ====================
+-- EVENTS
+-- ====================
+
+CREATE TABLE IF NOT EXISTS e_prescription_events (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    prescription_id UUID NOT NULL REFERENCES prescriptions(id) ON DELETE CASCADE,
+    patient_id UUID NOT NULL REFERENCES patients(id) ON DELETE CASCADE,
+    -- NULL for rejected submissions
+    nre VARCHAR(20),
+    status VARCHAR(30) NOT NULL CHECK (status IN (
+        'ISSUED', 'REJECTED', 'IN_CHARGE', 'PARTIALLY_DISPENSED', 'DISPENSED', 'CANCELLED', 'EXPIRED'
+    )),
+    source VARCHAR(20) NOT NULL CHECK (source IN ('SUBMISSION', 'CALLBACK', 'CANCELLATION')),
+    detail TEXT,                       -- 🔒 ENCRYPT
+    auth_code TEXT,                    -- 🔒 ENCRYPT (ISSUED events only)
+    -- What was submitted (submission events only), printed on the promemoria
+    aic_code VARCHAR(9),
+    exemption_code VARCHAR(6),
+    occurred_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    -- NULL for callbacks
+    created_by UUID REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_e_prescription_events_prescription
+    ON e_prescription_events (prescription_id, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_e_prescription_events_nre
+    ON e_prescription_events (nre)
+    WHERE nre IS NOT NULL;
+
+COMMENT ON TABLE e_prescription_events IS 'Sistema TS submissions, status callbacks and cancellations of prescriptions';
+COMMENT ON COLUMN e_prescription_events.detail IS '🔒 ENCRYPTED - Sistema TS errors or callback detail';
+COMMENT ON COLUMN e_prescription_events.auth_code IS '🔒 ENCRYPTED - Authentication code needed to cancel the NRE';
+
+-- ====================
+-- RLS
+-- ====================
+-- Status callbacks are applied as the system user (ADMIN)
+
+ALTER TABLE e_prescription_events ENABLE ROW LEVEL SECURITY;
+ALTER TABLE e_prescription_events FORCE ROW LEVEL SECURITY;
+
+CREATE POLICY e_prescription_events_select_policy ON e_prescription_events
+    FOR SELECT
+    USING (is_doctor() OR is_nurse());
+
+CREATE POLICY e_prescription_events_insert_policy ON e_prescription_events
+    FOR INSERT
+    WITH CHECK (is_doctor());
+
+-- Patient merges move the events with the prescriptions
+CREATE POLICY e_prescription_events_update_policy ON e_prescription_events
+    FOR UPDATE
+    USING (is_doctor())
+    WITH CHECK (is_doctor());
+
+GRANT SELECT, INSERT, UPDATE ON e_prescription_events TO mpms_user;
+
+-- ====================
+-- PROMEMORIA TEMPLATE
+-- ====================
+-- Not the default PRESCRIPTION template: the promemoria endpoint picks it by
+-- key and fills the nre.* variables.
+
+ALTER TABLE document_templates DISABLE ROW LEVEL SECURITY;
+
+INSERT INTO document_templates (
+    template_key, template_name, description, document_type,
+    template_html, template_variables, header_html, footer_html, css_styles,
+    page_size, page_orientation, margin_top_mm, margin_bottom_mm, margin_left_mm, margin_right_mm,
+    is_active, is_default, language
+) VALUES (
+    'nre_promemoria_it',
+    'Promemoria Ricetta Dematerializzata',
+    'Promemoria per l''assistito della ricetta elettronica dematerializzata (NRE)',
+    'PRESCRIPTION',
+    E'<div class="promemoria">
+    <h1 class="title">PROMEMORIA PER L''ASSISTITO</h1>
+    <p class="subtitle">Ricetta elettronica - Servizio Sanitario Nazionale</p>
+
+    <div class="codes">
+        <div class="code">
+            <p><strong>Codice NRE:</strong> {{nre.number}}</p>
+            {% if nre.barcode %}
+            <img class="barcode" src="{{nre.barcode}}" alt="{{nre.number}}">
+            {% endif %}
+        </div>
+        <div class="code">
+            <p><strong>Codice Fiscale:</strong> {{nre.fiscal_code}}</p>
+            {% if nre.fiscal_code_barcode %}
+            <img class="barcode" src="{{nre.fiscal_code_barcode}}" alt="{{nre.fiscal_code}}">
+            {% endif %}
+        </div>
+    </div>
+
+    <table class="info-table">
+        <tr>
+            <td><strong>Cognome e Nome:</strong></td>
+            <td>{{patient.full_name}}</td>
+            <td><strong>Data di nascita:</strong></td>
+            <td>{{patient.date_of_birth}}</td>
+        </tr>
+        <tr>
+            <td><strong>Esenzione:</strong></td>
+            <td>{% if nre.exemption_code %}{{nre.exemption_code}}{% else %}Non esente{% endif %}</td>
+            <td><strong>Data compilazione:</strong></td>
+            <td>{{nre.issued_at}}</td>
+        </tr>
+    </table>
+
+    <table class="items">
+        <thead>
+            <tr><th>AIC</th><th>Prescrizione</th><th>Quantità</th></tr>
+        </thead>
+        <tbody>
+            <tr>
+                <td>{{nre.aic_code}}</td>
+                <td>{{nre.medication}}{% if nre.dosage %} - {{nre.dosage}}{% endif %}</td>
+                <td>{{nre.quantity}}</td>
+            </tr>
+        </tbody>
+    </table>
+
+    {% if nre.instructions %}
+    <div class="instructions">
+        <p><strong>Posologia:</strong> {{nre.instructions}}</p>
+    </div>
+    {% endif %}
+
+    <div class="footer-section">
+        <div class="date-location">
+            <p>{{clinic.city}}, {{document.date}}</p>
+        </div>
+        <div class="signature">
+            <p>Il Medico Prescrittore</p>
+            <p>Dr. {{provider.full_name}}</p>
+            <p class="prescriber-cf">{{nre.prescriber_fiscal_code}}</p>
+        </div>
+    </div>
+</div>',
+    '{"required": ["patient", "provider", "clinic", "nre", "document"], "patient": ["full_name", "date_of_birth"], "provider": ["full_name"], "clinic": ["city"], "nre": ["number", "fiscal_code", "aic_code", "medication", "quantity"], "document": ["date"]}',
+    E'<div class="header">
+    <div class="clinic-info">
+        <h2>{{clinic.name}}</h2>
+        <p>{{clinic.address}} - {{clinic.city}} ({{clinic.province}})</p>
+    </div>
+</div>',
+    E'<div class="footer">
+    <p class="validity">Presentare il promemoria in farmacia: la ricetta è valida 30 giorni dalla data di compilazione</p>
+</div>',
+    E'.promemoria { font-family: Arial, sans-serif; line-height: 1.4; }
+.title { text-align: center; margin-bottom: 0; }
+.subtitle { text-align: center; color: #666; margin-top: 5px; }
+.codes { display: flex; justify-content: space-between; margin: 20px 0; }
+.code { text-align: center; }
+.barcode { height: 50px; }
+.info-table { width: 100%; border-collapse: collapse; margin: 10px 0; }
+.info-table td { padding: 5px; }
+.items { width: 100%; border-collapse: collapse; margin: 15px 0; }
+.items th, .items td { border: 1px solid #999; padding: 6px; text-align: left; }
+.instructions { margin: 10px 0; }
+.footer-section { margin-top: 30px; display: flex; justify-content: space-between; }
+.signature { text-align: center; }
+.prescriber-cf { font-size: 0.9em; color: #666; }
+.footer { text-align: center; border-top: 1px solid #ccc; padding-top: 10px; }
+.validity { font-style: italic; color: #666; }',
+    'A4', 'PORTRAIT', 15, 15, 15, 15,
+    true, false, 'it'
+) ON CONFLICT (template_key) DO NOTHING;
+
+ALTER TABLE document_templates ENABLE ROW LEVEL SECURITY;
+
+-- ====================
+-- CASBIN POLICIES
+-- ====================
+-- Same rules as casbin/policy.csv, for installs that already seeded the table
+
+INSERT INTO casbin_rules (ptype, v0, v1, v2)
+SELECT v.ptype, v.v0, v.v1, v.v2
+FROM (VALUES
+    ('p', 'ADMIN', 'prescriptions', 'e_prescribe'),
+    ('p', 'DOCTOR', 'prescriptions', 'e_prescribe'),
+    ('p', 'ADMIN', 'prescriptions', 'promemoria'),
+    ('p', 'DOCTOR', 'prescriptions', 'promemoria'),
+    ('p', 'NURSE', 'prescriptions', 'promemoria')
+) AS v (ptype, v0, v1, v2)
+WHERE EXISTS (SELECT 1 FROM casbin_rules)
+ON CONFLICT (ptype, v0, v1, v2, v3, v4, v5) DO NOTHING;
@@ -0,0 +1,192 @@
+-- Migration: SSN prescription print
+-- Date: 2026-04-08
+--
+-- SSN_PRESCRIPTION documents print a prescription on the Italian SSN
+-- "ricetta" layout: patient and fiscal code, exemption code, up to 2
+-- packages and the priority class. They are generated straight from a
+-- prescription (POST /api/v1/prescriptions/:id/ssn-print); the `ricetta`
+-- variables are built by DocumentService.
+
+-- ====================
+-- DOCUMENT TYPE
+-- ====================
+
+ALTER TABLE document_templates DROP CONSTRAINT IF EXISTS document_templates_document_type_check;
+ALTER TABLE document_templates ADD CONSTRAINT document_templates_document_type_check
+    CHECK (document_type IN (
+        'MEDICAL_CERTIFICATE', 'REFERRAL_LETTER', 'LAB_REQUEST', 'VISIT_SUMMARY', 'PRESCRIPTION',
+        'SSN_PRESCRIPTION', 'CUSTOM'
+    ));
+
+ALTER TABLE generated_documents DROP CONSTRAINT IF EXISTS generated_documents_document_type_check;
+ALTER TABLE generated_documents ADD CONSTRAINT generated_documents_document_type_check
+    CHECK (document_type IN (
+        'MEDICAL_CERTIFICATE', 'REFERRAL_LETTER', 'LAB_REQUEST', 'VISIT_SUMMARY', 'PRESCRIPTION',
+        'SSN_PRESCRIPTION', 'CUSTOM'
+    ));
+
+CREATE OR REPLACE FUNCTION generate_document_filename()
+RETURNS TRIGGER AS $$
+DECLARE
+    date_part VARCHAR(8);
+    time_part VARCHAR(6);
+    type_abbrev VARCHAR(10);
+BEGIN
+    IF NEW.document_filename IS NULL OR NEW.document_filename = '' THEN
+        date_part := TO_CHAR(NOW(), 'YYYYMMDD');
+        time_part := TO_CHAR(NOW(), 'HH24MISS');
+
+        -- Abbreviate document type
+        type_abbrev := CASE NEW.document_type
+            WHEN 'MEDICAL_CERTIFICATE' THEN 'med_cert'
+            WHEN 'REFERRAL_LETTER' THEN 'referral'
+            WHEN 'LAB_REQUEST' THEN 'lab_req'
+            WHEN 'VISIT_SUMMARY' THEN 'visit_sum'
+            WHEN 'PRESCRIPTION' THEN 'rx'
+            WHEN 'SSN_PRESCRIPTION' THEN 'ricetta'
+            ELSE 'doc'
+        END;
+
+        NEW.document_filename := type_abbrev || '_' || date_part || '_' || time_part || '.pdf';
+    END IF;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+-- ====================
+-- TEMPLATE
+-- ====================
+
+ALTER TABLE document_templates DISABLE ROW LEVEL SECURITY;
+
+INSERT INTO document_templates (
+    template_key, template_name, description, document_type,
+    template_html, template_variables, header_html, footer_html, css_styles,
+    page_size, page_orientation, margin_top_mm, margin_bottom_mm, margin_left_mm, margin_right_mm,
+    is_active, is_default, language
+) VALUES (
+    'ssn_prescription_it',
+    'Ricetta SSN',
+    'Ricetta del Servizio Sanitario Nazionale (modello ricetta rossa)',
+    'SSN_PRESCRIPTION',
+    E'<div class="ricetta">
+    <div class="ricetta-header">
+        <p class="ssn">SERVIZIO SANITARIO NAZIONALE</p>
+        {% if ricetta.nre %}
+        <p class="nre">NRE {{ricetta.nre}}</p>
+        {% endif %}
+    </div>
+
+    <table class="grid">
+        <tr>
+            <td class="label">Cognome e Nome dell''assistito</td>
+            <td colspan="3" class="value">{{patient.last_name | upper}} {{patient.first_name | upper}}</td>
+        </tr>
+        <tr>
+            <td class="label">Codice Fiscale</td>
+            <td class="value fiscal-code">
+                {% for c in ricetta.fiscal_code_boxes %}<span class="box">{{c}}</span>{% endfor %}
+            </td>
+            <td class="label">Data di nascita</td>
+            <td class="value">{{patient.date_of_birth}}</td>
+        </tr>
+        <tr>
+            <td class="label">Esenzione</td>
+            <td class="value">
+                {% if ricetta.exemption_code %}{{ricetta.exemption_code}}{% else %}<span class="box">N</span> Non esente{% endif %}
+            </td>
+            <td class="label">Priorità</td>
+            <td class="value">
+                {% for p in ricetta.priority_boxes %}<span class="box{% if p.selected %} checked{% endif %}">{{p.code}}</span>{% endfor %}
+            </td>
+        </tr>
+    </table>
+
+    <table class="items">
+        <thead>
+            <tr><th class="rx">Prescrizione</th><th class="qty">N. confezioni</th></tr>
+        </thead>
+        <tbody>
+            {% for item in ricetta.items %}
+            <tr>
+                <td>
+                    <strong>{{item.description}}</strong>
+                    {% if item.form %}<span class="form"> - {{item.form}}</span>{% endif %}
+                    <p class="posology">{{item.posology}}</p>
+                </td>
+                <td class="qty">{{item.packages}}</td>
+            </tr>
+            {% endfor %}
+        </tbody>
+    </table>
+
+    <p class="total">Totale confezioni: <strong>{{ricetta.total_packages}}</strong></p>
+
+    {% if ricetta.notes %}
+    <p class="notes">{{ricetta.notes}}</p>
+    {% endif %}
+
+    <div class="footer-section">
+        <div class="date-location">
+            <p>{{clinic.city}}, {{ricetta.date}}</p>
+        </div>
+        <div class="signature">
+            <p>Timbro e firma del medico</p>
+            <p class="signature-line">_________________________</p>
+            <p>Dr. {{provider.full_name}}</p>
+        </div>
+    </div>
+</div>',
+    '{"required": ["patient", "provider", "clinic", "ricetta"], "patient": ["first_name", "last_name", "date_of_birth"], "provider": ["full_name"], "clinic": ["city"], "ricetta": ["fiscal_code_boxes", "priority_boxes", "items", "total_packages", "date"]}',
+    E'<div class="header">
+    <div class="clinic-info">
+        <h2>{{clinic.name}}</h2>
+        <p>{{clinic.address}} - {{clinic.city}} ({{clinic.province}})</p>
+    </div>
+</div>',
+    E'<div class="footer">
+    <p class="validity">Ricetta valida 30 giorni dalla data di compilazione, esclusa la data di rilascio</p>
+</div>',
+    E'.ricetta { font-family: Arial, sans-serif; line-height: 1.4; color: #000; border: 2px solid #c0392b; padding: 10px; }
+.ricetta-header { display: flex; justify-content: space-between; border-bottom: 1px solid #c0392b; margin-bottom: 10px; }
+.ssn { font-weight: bold; color: #c0392b; letter-spacing: 1px; }
+.nre { font-family: monospace; }
+.grid { width: 100%; border-collapse: collapse; }
+.grid td { border: 1px solid #c0392b; padding: 4px; vertical-align: top; }
+.label { font-size: 0.75em; color: #c0392b; width: 18%; }
+.value { font-weight: bold; }
+.box { display: inline-block; min-width: 12px; border: 1px solid #c0392b; text-align: center; margin-right: 1px; font-family: monospace; }
+.box.checked { background-color: #000; color: #fff; }
+.items { width: 100%; border-collapse: collapse; margin-top: 10px; }
+.items th { font-size: 0.75em; color: #c0392b; text-align: left; border-bottom: 1px solid #c0392b; }
+.items td { padding: 6px 4px; border-bottom: 1px dotted #c0392b; }
+.qty { width: 15%; text-align: center; }
+.posology { margin: 3px 0 0 0; font-size: 0.9em; }
+.form { font-style: italic; }
+.total { text-align: right; margin: 8px 0; }
+.notes { font-size: 0.9em; }
+.footer-section { display: flex; justify-content: space-between; margin-top: 25px; }
+.signature { text-align: center; }
+.signature-line { margin: 20px 0 5px 0; }
+.footer { text-align: center; border-top: 1px solid #ccc; padding-top: 10px; }
+.validity { font-style: italic; color: #666; font-size: 0.85em; }',
+    'A4', 'PORTRAIT', 15, 15, 15, 15,
+    true, true, 'it'
+) ON CONFLICT (template_key) DO NOTHING;
+
+ALTER TABLE document_templates ENABLE ROW LEVEL SECURITY;
+
+-- ====================
+-- CASBIN POLICIES
+-- ====================
+-- Same rules as casbin/policy.csv, for installs that already seeded the table
+
+INSERT INTO casbin_rules (ptype, v0, v1, v2)
+SELECT v.ptype, v.v0, v.v1, v.v2
+FROM (VALUES
+    ('p', 'ADMIN', 'prescriptions', 'print'),
+    ('p', 'DOCTOR', 'prescriptions', 'print')
+) AS v (ptype, v0, v1, v2)
+WHERE EXISTS (SELECT 1 FROM casbin_rules)
+ON CONFLICT (ptype, v0, v1, v2, v3, v4, v5) DO NOTHING;
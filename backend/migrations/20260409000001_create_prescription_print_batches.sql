@@ -0,0 +1,34 @@
+-- Migration: Prescription Print Batches
+-- Date: 2026-04-09
+--
+-- POST /api/v1/prescriptions/print-batches merges a list of prescriptions
+-- (or all of today's) into one PDF, one prescription per page, to print and
+-- sign them in one go. Small batches are rendered during the request, larger
+-- ones in the background; each batch is tracked here until its PDF is ready.
+
+CREATE TABLE IF NOT EXISTS prescription_print_batches (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    requested_by UUID NOT NULL REFERENCES users(id),
+    status VARCHAR(20) NOT NULL DEFAULT 'PROCESSING' CHECK (
+        status IN ('PROCESSING', 'COMPLETED', 'FAILED')
+    ),
+    layout VARCHAR(20) NOT NULL DEFAULT 'STANDARD' CHECK (
+        layout IN ('STANDARD', 'SSN')
+    ),
+    prescription_ids UUID[] NOT NULL,
+    file_path TEXT,
+    file_size_bytes BIGINT,
+    file_hash VARCHAR(64),
+    error_message TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_prescription_print_batches_requested_by
+    ON prescription_print_batches (requested_by, created_at DESC);
+
+COMMENT ON TABLE prescription_print_batches IS 'Prescriptions merged into one PDF for batch printing/signing';
+COMMENT ON COLUMN prescription_print_batches.prescription_ids IS 'Prescriptions in print order, one per page';
+COMMENT ON COLUMN prescription_print_batches.file_hash IS 'SHA-256 (hex) of the merged PDF';
+
+GRANT SELECT, INSERT, UPDATE ON prescription_print_batches TO mpms_user;
@@ -0,0 +1,55 @@
+-- Migration: Prescription refill tracking
+-- Date: 2026-04-10
+--
+-- days_supply is how many days the prescribed quantity lasts, estimated from
+-- the (encrypted) frequency and the quantity when the prescription is saved.
+-- The expected refill date follows from it: days_supply after the last
+-- refill, or after the start of therapy before the first one. Recording a
+-- refill (POST /api/v1/prescriptions/:id/refill) decrements
+-- refills_remaining, which sets last_refill_date and moves the date forward.
+--
+-- Active prescriptions past their expected refill date are overdue; doctors
+-- can email those patients a REFILL_REMINDER, at most once per reminder
+-- interval (last_refill_reminder_at).
+
+ALTER TABLE prescriptions
+    ADD COLUMN IF NOT EXISTS days_supply INT CHECK (days_supply > 0),
+    ADD COLUMN IF NOT EXISTS expected_refill_date DATE
+        GENERATED ALWAYS AS (COALESCE(last_refill_date, start_date, prescribed_date) + days_supply) STORED,
+    ADD COLUMN IF NOT EXISTS last_refill_reminder_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_prescriptions_expected_refill
+    ON prescriptions (expected_refill_date)
+    WHERE status = 'ACTIVE' AND expected_refill_date IS NOT NULL;
+
+COMMENT ON COLUMN prescriptions.days_supply IS 'Days the prescribed quantity lasts, estimated from frequency and quantity (NULL if unknown, e.g. as needed)';
+COMMENT ON COLUMN prescriptions.expected_refill_date IS 'Date the current supply runs out';
+COMMENT ON COLUMN prescriptions.last_refill_reminder_at IS 'Last REFILL_REMINDER queued for this prescription';
+
+-- ====================
+-- REMINDERS
+-- ====================
+
+ALTER TABLE notification_queue
+    DROP CONSTRAINT IF EXISTS notification_queue_notification_type_check;
+ALTER TABLE notification_queue
+    ADD CONSTRAINT notification_queue_notification_type_check CHECK (
+        notification_type IN ('APPOINTMENT_REMINDER', 'APPOINTMENT_BOOKED', 'APPOINTMENT_CONFIRMATION',
+                              'APPOINTMENT_CANCELLATION', 'VISIT_SUMMARY', 'PRESCRIPTION_READY',
+                              'FOLLOW_UP_REMINDER', 'CUSTOM', 'MARKETING', 'REFERRAL_REMINDER',
+                              'REFILL_REMINDER')
+    );
+
+-- ====================
+-- CASBIN POLICIES
+-- ====================
+-- Same rules as casbin/policy.csv, for installs that already seeded the table
+
+INSERT INTO casbin_rules (ptype, v0, v1, v2)
+SELECT v.ptype, v.v0, v.v1, v.v2
+FROM (VALUES
+    ('p', 'ADMIN', 'prescriptions', 'remind'),
+    ('p', 'DOCTOR', 'prescriptions', 'remind')
+) AS v (ptype, v0, v1, v2)
+WHERE EXISTS (SELECT 1 FROM casbin_rules)
+ON CONFLICT (ptype, v0, v1, v2, v3, v4, v5) DO NOTHING;
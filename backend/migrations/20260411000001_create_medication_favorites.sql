@@ -0,0 +1,66 @@
+-- Migration: Medication favorites and prescribing usage
+-- Date: 2026-04-11
+--
+-- Each prescriber can keep favorite medications with default dosing; they
+-- come first in medication search and in the quick-pick list, and prefill
+-- the prescription form. Below them, medications the prescriber has written
+-- before are ranked by how often.
+--
+-- Prescriptions store the medication name encrypted, so usage cannot be
+-- counted from them in SQL: medication_usage keeps a per-prescriber counter,
+-- bumped when a prescription is created and learned once from the
+-- prescriber's existing prescriptions. Neither table references a patient,
+-- so, like prescription_templates, they are not encrypted.
+
+CREATE TABLE IF NOT EXISTS medication_favorites (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+
+    medication_name VARCHAR(255) NOT NULL,
+    generic_name VARCHAR(255),
+    form VARCHAR(50),
+    route VARCHAR(50),
+
+    -- Default dosing
+    dosage VARCHAR(100),
+    frequency VARCHAR(100),
+    duration VARCHAR(100),
+    quantity INT CHECK (quantity IS NULL OR quantity BETWEEN 1 AND 1000),
+    refills INT CHECK (refills IS NULL OR refills BETWEEN 0 AND 12),
+    instructions TEXT,
+
+    sort_order INT NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- One favorite per medication and prescriber
+CREATE UNIQUE INDEX IF NOT EXISTS idx_medication_favorites_user_medication
+    ON medication_favorites (user_id, LOWER(medication_name));
+
+CREATE TRIGGER update_medication_favorites_updated_at
+    BEFORE UPDATE ON medication_favorites
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
+
+COMMENT ON TABLE medication_favorites IS 'Per-prescriber favorite medications with default dosing (not encrypted, no patient data)';
+COMMENT ON COLUMN medication_favorites.sort_order IS 'Position in the quick-pick list (ascending)';
+
+CREATE TABLE IF NOT EXISTS medication_usage (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    -- Lowercased, trimmed medication name
+    medication_key VARCHAR(255) NOT NULL,
+    -- Name as last prescribed
+    medication_name VARCHAR(255) NOT NULL,
+    prescription_count INT NOT NULL DEFAULT 0,
+    last_prescribed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, medication_key)
+);
+
+CREATE INDEX IF NOT EXISTS idx_medication_usage_user_count
+    ON medication_usage (user_id, prescription_count DESC);
+
+COMMENT ON TABLE medication_usage IS 'How often each prescriber has prescribed each medication (ranks medication search)';
+
+GRANT SELECT, INSERT, UPDATE, DELETE ON medication_favorites TO mpms_user;
+GRANT SELECT, INSERT, UPDATE ON medication_usage TO mpms_user;
@@ -0,0 +1,118 @@
+-- Migration: Visit addenda
+-- Date: 2026-04-12
+--
+-- Signed and locked visits cannot be edited. Corrections and late additions
+-- are appended as addenda instead: each one is signed on creation by its
+-- author (signature_hash chains it to the visit's signature) and the
+-- original note is left untouched.
+--
+-- ADDENDUM adds information; CORRECTION amends something in the note and
+-- needs a reason. Addenda can never be changed or removed; a patient merge
+-- only moves them to the surviving patient along with their visit.
+
+CREATE TABLE IF NOT EXISTS visit_addenda (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    visit_id UUID NOT NULL,
+    visit_date DATE NOT NULL,  -- Required for partition key in visits table
+    patient_id UUID NOT NULL REFERENCES patients(id) ON DELETE CASCADE,
+    addendum_type VARCHAR(20) NOT NULL CHECK (addendum_type IN ('ADDENDUM', 'CORRECTION')),
+    reason TEXT,                       -- 🔒 ENCRYPT
+    content TEXT NOT NULL,             -- 🔒 ENCRYPT
+
+    signed_by UUID NOT NULL REFERENCES users(id),
+    signed_at TIMESTAMPTZ NOT NULL,
+    signature_hash VARCHAR(64) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    FOREIGN KEY (visit_id, visit_date) REFERENCES visits(id, visit_date) ON DELETE CASCADE,
+    CONSTRAINT visit_addenda_correction_reason CHECK (
+        addendum_type <> 'CORRECTION' OR reason IS NOT NULL
+    )
+);
+
+CREATE INDEX IF NOT EXISTS idx_visit_addenda_visit ON visit_addenda (visit_id, signed_at);
+
+COMMENT ON TABLE visit_addenda IS 'Signed amendments appended to signed/locked visits; the visit note itself is never changed';
+COMMENT ON COLUMN visit_addenda.reason IS '🔒 ENCRYPTED - Why the note is corrected (required for CORRECTION)';
+COMMENT ON COLUMN visit_addenda.content IS '🔒 ENCRYPTED - Text of the addendum';
+COMMENT ON COLUMN visit_addenda.signature_hash IS 'SHA-256 over the visit signature, author, time, type, reason and content';
+
+-- Only signed or locked visits take addenda
+CREATE OR REPLACE FUNCTION check_visit_addendum_target()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF NOT EXISTS (
+        SELECT 1 FROM visits
+        WHERE id = NEW.visit_id AND visit_date = NEW.visit_date AND status IN ('SIGNED', 'LOCKED')
+    ) THEN
+        RAISE EXCEPTION 'Addenda can only be added to signed or locked visits';
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trigger_check_visit_addendum_target
+    BEFORE INSERT ON visit_addenda
+    FOR EACH ROW
+    EXECUTE FUNCTION check_visit_addendum_target();
+
+-- Addenda are immutable, except for re-parenting by a patient merge
+CREATE OR REPLACE FUNCTION prevent_visit_addendum_modification()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF OLD.patient_id::TEXT = current_setting('app.merge_patient_id', TRUE)
+       AND NEW.patient_id::TEXT = current_setting('app.merge_into_patient_id', TRUE)
+       AND (to_jsonb(NEW) - 'patient_id') = (to_jsonb(OLD) - 'patient_id') THEN
+        RETURN NEW;
+    END IF;
+
+    RAISE EXCEPTION 'Visit addenda cannot be modified';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trigger_prevent_visit_addendum_modification
+    BEFORE UPDATE ON visit_addenda
+    FOR EACH ROW
+    EXECUTE FUNCTION prevent_visit_addendum_modification();
+
+CREATE TRIGGER trigger_prevent_anonymized_patient_visit_addendum
+    BEFORE INSERT ON visit_addenda
+    FOR EACH ROW
+    EXECUTE FUNCTION prevent_anonymized_patient_reference();
+
+-- ====================
+-- RLS
+-- ====================
+-- Append-only: no DELETE, and UPDATE only for a patient merge
+
+ALTER TABLE visit_addenda ENABLE ROW LEVEL SECURITY;
+ALTER TABLE visit_addenda FORCE ROW LEVEL SECURITY;
+
+CREATE POLICY visit_addenda_select_policy ON visit_addenda
+    FOR SELECT
+    USING (is_doctor() OR is_nurse());
+
+CREATE POLICY visit_addenda_insert_policy ON visit_addenda
+    FOR INSERT
+    WITH CHECK (is_doctor());
+
+CREATE POLICY visit_addenda_merge_update_policy ON visit_addenda
+    FOR UPDATE
+    USING (is_admin() AND patient_id::TEXT = current_setting('app.merge_patient_id', TRUE))
+    WITH CHECK (is_admin() AND patient_id::TEXT = current_setting('app.merge_into_patient_id', TRUE));
+
+GRANT SELECT, INSERT, UPDATE ON visit_addenda TO mpms_user;
+
+-- ====================
+-- CASBIN POLICIES
+-- ====================
+-- Same rules as casbin/policy.csv, for installs that already seeded the table
+
+INSERT INTO casbin_rules (ptype, v0, v1, v2)
+SELECT v.ptype, v.v0, v.v1, v.v2
+FROM (VALUES
+    ('p', 'ADMIN', 'visits', 'amend'),
+    ('p', 'DOCTOR', 'visits', 'amend')
+) AS v (ptype, v0, v1, v2)
+WHERE EXISTS (SELECT 1 FROM casbin_rules)
+ON CONFLICT (ptype, v0, v1, v2, v3, v4, v5) DO NOTHING;
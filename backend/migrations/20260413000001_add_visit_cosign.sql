@@ -0,0 +1,244 @@
+-- Migration: Visit co-signature
+-- Date: 2026-04-13
+--
+-- Users under supervision (trainees, or nurses documenting visits) have a
+-- co-signature requirement naming their supervising doctor. When such a
+-- user signs a visit it goes to PENDING_COSIGN instead of SIGNED: the note
+-- is frozen, and the supervisor either countersigns it (→ SIGNED) or
+-- returns it to DRAFT with a note. Only SIGNED visits are final.
+--
+--   DRAFT → PENDING_COSIGN → SIGNED → LOCKED
+--                 ↓
+--               DRAFT (returned)
+--
+-- The supervisor is copied onto the visit on submission, so changing a
+-- requirement does not move visits already waiting.
+
+-- ====================
+-- CO-SIGNATURE REQUIREMENTS
+-- ====================
+
+CREATE TABLE IF NOT EXISTS visit_cosign_requirements (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    supervisor_id UUID NOT NULL REFERENCES users(id),
+    created_by UUID REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT visit_cosign_requirements_not_self CHECK (user_id <> supervisor_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_visit_cosign_requirements_supervisor
+    ON visit_cosign_requirements (supervisor_id);
+
+CREATE TRIGGER update_visit_cosign_requirements_updated_at
+    BEFORE UPDATE ON visit_cosign_requirements
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
+
+COMMENT ON TABLE visit_cosign_requirements IS 'Users whose signed visits must be countersigned by their supervising doctor';
+
+GRANT SELECT, INSERT, UPDATE, DELETE ON visit_cosign_requirements TO mpms_user;
+
+-- ====================
+-- VISITS
+-- ====================
+
+ALTER TABLE visits
+    ADD COLUMN IF NOT EXISTS cosign_supervisor_id UUID REFERENCES users(id),
+    ADD COLUMN IF NOT EXISTS cosigned_by UUID REFERENCES users(id),
+    ADD COLUMN IF NOT EXISTS cosigned_at TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS cosign_return_note TEXT;  -- 🔒 ENCRYPT
+
+ALTER TABLE visits DROP CONSTRAINT IF EXISTS visits_status_check;
+ALTER TABLE visits
+    ADD CONSTRAINT visits_status_check CHECK (
+        status IN ('DRAFT', 'PENDING_COSIGN', 'SIGNED', 'LOCKED')
+    );
+
+ALTER TABLE visits DROP CONSTRAINT IF EXISTS valid_signature;
+ALTER TABLE visits
+    ADD CONSTRAINT valid_signature CHECK (
+        (status IN ('PENDING_COSIGN', 'SIGNED', 'LOCKED') AND signed_at IS NOT NULL AND signed_by IS NOT NULL) OR
+        (status = 'DRAFT')
+    );
+
+ALTER TABLE visits DROP CONSTRAINT IF EXISTS valid_cosign;
+ALTER TABLE visits
+    ADD CONSTRAINT valid_cosign CHECK (
+        (status <> 'PENDING_COSIGN' OR cosign_supervisor_id IS NOT NULL)
+        AND ((cosigned_by IS NULL) = (cosigned_at IS NULL))
+    );
+
+CREATE INDEX IF NOT EXISTS idx_visits_cosign_queue
+    ON visits (cosign_supervisor_id, signed_at)
+    WHERE status = 'PENDING_COSIGN';
+
+COMMENT ON COLUMN visits.status IS 'Visit status: DRAFT → (PENDING_COSIGN →) SIGNED → LOCKED';
+COMMENT ON COLUMN visits.cosign_supervisor_id IS 'Supervisor who must countersign (set when submitted for co-signature)';
+COMMENT ON COLUMN visits.cosigned_by IS 'Supervisor who countersigned the visit';
+COMMENT ON COLUMN visits.cosign_return_note IS '🔒 ENCRYPTED - Why the supervisor returned the visit to draft';
+
+-- Same rules as before, plus the co-signature steps: a visit awaiting
+-- co-signature only changes to be countersigned or returned, and neither
+-- touches the note
+CREATE OR REPLACE FUNCTION prevent_signed_visit_modification()
+RETURNS TRIGGER AS $$
+BEGIN
+    -- Allow re-parenting by a patient merge
+    IF OLD.patient_id::TEXT = current_setting('app.merge_patient_id', TRUE)
+       AND NEW.patient_id::TEXT = current_setting('app.merge_into_patient_id', TRUE)
+       AND (to_jsonb(NEW) - 'patient_id' - 'updated_at') = (to_jsonb(OLD) - 'patient_id' - 'updated_at') THEN
+        RETURN NEW;
+    END IF;
+
+    -- Allow status transitions from DRAFT to SIGNED or PENDING_COSIGN
+    IF OLD.status = 'DRAFT' AND NEW.status IN ('SIGNED', 'PENDING_COSIGN') THEN
+        RETURN NEW;
+    END IF;
+
+    -- Allow countersigning or returning a visit awaiting co-signature
+    IF OLD.status = 'PENDING_COSIGN' AND NEW.status IN ('SIGNED', 'DRAFT')
+       AND (to_jsonb(NEW) - 'status' - 'signed_at' - 'signed_by' - 'signature_hash'
+                - 'cosigned_by' - 'cosigned_at' - 'cosign_return_note' - 'updated_at' - 'updated_by')
+         = (to_jsonb(OLD) - 'status' - 'signed_at' - 'signed_by' - 'signature_hash'
+                - 'cosigned_by' - 'cosigned_at' - 'cosign_return_note' - 'updated_at' - 'updated_by') THEN
+        RETURN NEW;
+    END IF;
+
+    IF OLD.status = 'PENDING_COSIGN' THEN
+        RAISE EXCEPTION 'Cannot modify visit awaiting co-signature. Countersign it or return it to draft.';
+    END IF;
+
+    -- Allow status transitions from SIGNED to LOCKED
+    IF OLD.status = 'SIGNED' AND NEW.status = 'LOCKED' THEN
+        RETURN NEW;
+    END IF;
+
+    -- Prevent any changes to SIGNED visits except locking
+    IF OLD.status = 'SIGNED' AND NEW.status != 'LOCKED' THEN
+        RAISE EXCEPTION 'Cannot modify signed visit. Create a new version or lock it.';
+    END IF;
+
+    -- Prevent any changes to LOCKED visits
+    IF OLD.status = 'LOCKED' THEN
+        RAISE EXCEPTION 'Cannot modify locked visit. Visit is permanently locked.';
+    END IF;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION validate_visit_status_transition()
+RETURNS TRIGGER AS $$
+BEGIN
+    -- DRAFT can go to: SIGNED, PENDING_COSIGN
+    IF OLD.status = 'DRAFT' AND NEW.status NOT IN ('DRAFT', 'SIGNED', 'PENDING_COSIGN') THEN
+        RAISE EXCEPTION 'Invalid status transition from DRAFT to %. Must sign first.', NEW.status;
+    END IF;
+
+    -- A signer under co-signature cannot sign directly
+    IF OLD.status = 'DRAFT' AND NEW.status = 'SIGNED' AND EXISTS (
+        SELECT 1 FROM visit_cosign_requirements
+        WHERE user_id = COALESCE(NEW.signed_by, NEW.updated_by)
+    ) THEN
+        RAISE EXCEPTION 'Visits signed by % require co-signature', COALESCE(NEW.signed_by, NEW.updated_by);
+    END IF;
+
+    -- PENDING_COSIGN can go to: SIGNED (countersigned), DRAFT (returned)
+    IF OLD.status = 'PENDING_COSIGN' AND NEW.status NOT IN ('PENDING_COSIGN', 'SIGNED', 'DRAFT') THEN
+        RAISE EXCEPTION 'Invalid status transition from PENDING_COSIGN to %. Must be countersigned first.', NEW.status;
+    END IF;
+
+    IF OLD.status = 'PENDING_COSIGN' AND NEW.status = 'SIGNED' AND NEW.cosigned_by IS NULL THEN
+        RAISE EXCEPTION 'A visit awaiting co-signature must be countersigned';
+    END IF;
+
+    -- SIGNED can go to: LOCKED
+    IF OLD.status = 'SIGNED' AND NEW.status NOT IN ('SIGNED', 'LOCKED') THEN
+        RAISE EXCEPTION 'Invalid status transition from SIGNED to %. Can only lock.', NEW.status;
+    END IF;
+
+    -- LOCKED is final
+    IF OLD.status = 'LOCKED' AND NEW.status != 'LOCKED' THEN
+        RAISE EXCEPTION 'Cannot change status from LOCKED';
+    END IF;
+
+    -- Auto-set signature fields when signing
+    IF NEW.status IN ('SIGNED', 'PENDING_COSIGN') AND OLD.status = 'DRAFT' THEN
+        IF NEW.signed_at IS NULL THEN
+            NEW.signed_at := NOW();
+        END IF;
+        IF NEW.signed_by IS NULL THEN
+            NEW.signed_by := NEW.updated_by;
+        END IF;
+        -- Generate signature hash from content using SHA-256 (cryptographically secure)
+        IF NEW.signature_hash IS NULL THEN
+            NEW.signature_hash := encode(
+                digest(
+                    COALESCE(NEW.subjective, '') ||
+                    COALESCE(NEW.objective, '') ||
+                    COALESCE(NEW.assessment, '') ||
+                    COALESCE(NEW.plan, ''),
+                    'sha256'
+                ),
+                'hex'
+            );
+        END IF;
+    END IF;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+-- ====================
+-- RLS
+-- ====================
+
+-- The supervisor sees and countersigns the visits waiting for them;
+-- admins can stand in for an absent supervisor
+DROP POLICY IF EXISTS visits_cosign_select_policy ON visits;
+CREATE POLICY visits_cosign_select_policy ON visits
+    FOR SELECT
+    USING (is_doctor() AND cosign_supervisor_id = get_current_user_id());
+
+DROP POLICY IF EXISTS visits_cosign_update_policy ON visits;
+CREATE POLICY visits_cosign_update_policy ON visits
+    FOR UPDATE
+    USING (
+        status = 'PENDING_COSIGN'
+        AND (is_admin() OR (is_doctor() AND cosign_supervisor_id = get_current_user_id()))
+    )
+    WITH CHECK (
+        status IN ('SIGNED', 'DRAFT')
+        AND (is_admin() OR (is_doctor() AND cosign_supervisor_id = get_current_user_id()))
+    );
+
+-- A nurse under co-signature submits the drafts they documented
+DROP POLICY IF EXISTS visits_nurse_cosign_policy ON visits;
+CREATE POLICY visits_nurse_cosign_policy ON visits
+    FOR UPDATE
+    USING (is_nurse() AND status = 'DRAFT' AND created_by = get_current_user_id())
+    WITH CHECK (
+        is_nurse()
+        AND status = 'PENDING_COSIGN'
+        AND EXISTS (
+            SELECT 1 FROM visit_cosign_requirements
+            WHERE user_id = get_current_user_id()
+        )
+    );
+
+-- ====================
+-- CASBIN POLICIES
+-- ====================
+-- Same rules as casbin/policy.csv, for installs that already seeded the table
+
+INSERT INTO casbin_rules (ptype, v0, v1, v2)
+SELECT v.ptype, v.v0, v.v1, v.v2
+FROM (VALUES
+    ('p', 'ADMIN', 'visits', 'cosign'),
+    ('p', 'ADMIN', 'visits', 'manage_cosign'),
+    ('p', 'DOCTOR', 'visits', 'cosign'),
+    ('p', 'NURSE', 'visits', 'sign')
+) AS v (ptype, v0, v1, v2)
+WHERE EXISTS (SELECT 1 FROM casbin_rules)
+ON CONFLICT (ptype, v0, v1, v2, v3, v4, v5) DO NOTHING;
@@ -0,0 +1,124 @@
+-- Migration: Visit attachments
+-- Date: 2026-04-14
+--
+-- Clinical photos (e.g. dermatology), audio memos and files taken during a
+-- visit. The file is stored through uploaded_files, encrypted at rest, and
+-- is only reachable through /api/v1/visits/:id/attachments. The kind
+-- (PHOTO, AUDIO, FILE) follows the detected file type.
+--
+-- Attachments belong to the visit note: they are added and removed while
+-- the visit is a DRAFT and frozen with it once signed (later material goes
+-- into an addendum). Deleting one only marks it deleted and keeps the file.
+
+ALTER TYPE file_purpose ADD VALUE IF NOT EXISTS 'VISIT_ATTACHMENT';
+
+CREATE TABLE IF NOT EXISTS visit_attachments (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    visit_id UUID NOT NULL,
+    visit_date DATE NOT NULL,  -- Required for partition key in visits table
+    patient_id UUID NOT NULL REFERENCES patients(id) ON DELETE CASCADE,
+    file_id UUID NOT NULL REFERENCES uploaded_files(id),
+    kind VARCHAR(10) NOT NULL CHECK (kind IN ('PHOTO', 'AUDIO', 'FILE')),
+    caption TEXT,                      -- 🔒 ENCRYPT
+    -- Body site of a clinical photo (e.g. "left forearm"), for comparisons over time
+    body_site VARCHAR(100),
+
+    created_by UUID NOT NULL REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    deleted_at TIMESTAMPTZ,
+    deleted_by UUID REFERENCES users(id),
+
+    FOREIGN KEY (visit_id, visit_date) REFERENCES visits(id, visit_date) ON DELETE CASCADE,
+    CONSTRAINT visit_attachments_deletion CHECK ((deleted_at IS NULL) = (deleted_by IS NULL))
+);
+
+CREATE INDEX IF NOT EXISTS idx_visit_attachments_visit
+    ON visit_attachments (visit_id, created_at)
+    WHERE deleted_at IS NULL;
+CREATE INDEX IF NOT EXISTS idx_visit_attachments_patient
+    ON visit_attachments (patient_id)
+    WHERE deleted_at IS NULL;
+
+COMMENT ON TABLE visit_attachments IS 'Clinical photos, audio memos and files of a visit; content encrypted in uploaded_files';
+COMMENT ON COLUMN visit_attachments.caption IS '🔒 ENCRYPTED - What the attachment shows or records';
+
+-- Attachments change only while their visit is a draft, except for
+-- re-parenting by a patient merge
+CREATE OR REPLACE FUNCTION check_visit_attachment_target()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'UPDATE'
+       AND OLD.patient_id::TEXT = current_setting('app.merge_patient_id', TRUE)
+       AND NEW.patient_id::TEXT = current_setting('app.merge_into_patient_id', TRUE)
+       AND (to_jsonb(NEW) - 'patient_id') = (to_jsonb(OLD) - 'patient_id') THEN
+        RETURN NEW;
+    END IF;
+
+    IF NOT EXISTS (
+        SELECT 1 FROM visits
+        WHERE id = NEW.visit_id AND visit_date = NEW.visit_date AND status = 'DRAFT'
+    ) THEN
+        RAISE EXCEPTION 'Attachments can only be changed on draft visits';
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trigger_check_visit_attachment_target
+    BEFORE INSERT OR UPDATE ON visit_attachments
+    FOR EACH ROW
+    EXECUTE FUNCTION check_visit_attachment_target();
+
+-- No new attachments for erased or merged patients
+CREATE TRIGGER trigger_prevent_anonymized_patient_visit_attachment
+    BEFORE INSERT ON visit_attachments
+    FOR EACH ROW
+    EXECUTE FUNCTION prevent_anonymized_patient_reference();
+
+-- ====================
+-- RLS
+-- ====================
+-- Same access as patient attachments: doctors and nurses read and add,
+-- doctors delete (soft) and admins re-parent on a merge
+
+ALTER TABLE visit_attachments ENABLE ROW LEVEL SECURITY;
+ALTER TABLE visit_attachments FORCE ROW LEVEL SECURITY;
+
+CREATE POLICY visit_attachments_select_policy ON visit_attachments
+    FOR SELECT
+    USING (is_doctor() OR is_nurse());
+
+CREATE POLICY visit_attachments_insert_policy ON visit_attachments
+    FOR INSERT
+    WITH CHECK (is_doctor() OR is_nurse());
+
+CREATE POLICY visit_attachments_update_policy ON visit_attachments
+    FOR UPDATE
+    USING (is_doctor())
+    WITH CHECK (is_doctor());
+
+GRANT SELECT, INSERT, UPDATE ON visit_attachments TO mpms_user;
+
+-- ====================
+-- CHART BUNDLE TEMPLATE
+-- ====================
+-- List each visit's attachments under its plan in the default Italian
+-- chart bundle
+
+UPDATE document_templates
+SET template_html = replace(
+        template_html,
+        '{% if visit.plan %}<p><strong>Piano:</strong> {{visit.plan}}</p>{% endif %}',
+        E'{% if visit.plan %}<p><strong>Piano:</strong> {{visit.plan}}</p>{% endif %}
+        {% if visit.attachments %}
+        <p><strong>Allegati:</strong></p>
+        <ul>
+        {% for attachment in visit.attachments %}
+            <li>{% if attachment.kind == "PHOTO" %}Foto{% elif attachment.kind == "AUDIO" %}Memo vocale{% else %}Documento{% endif %}{% if attachment.body_site %} ({{attachment.body_site}}){% endif %}: {% if attachment.caption %}{{attachment.caption}}{% else %}{{attachment.filename}}{% endif %}</li>
+        {% endfor %}
+        </ul>
+        {% endif %}'
+    ),
+    updated_at = NOW()
+WHERE template_key = 'patient_chart_bundle_it'
+  AND template_html NOT LIKE '%visit.attachments%';
@@ -0,0 +1,72 @@
+-- Migration: Visit audio memo transcription
+-- Date: 2026-04-15
+--
+-- Transcription of dictated audio memos, run by a background job when
+-- TRANSCRIPTION_PROVIDER is set. The transcript is split into a proposal
+-- for the visit's subjective and objective fields that the user reviews
+-- and applies through the normal visit update; the job never writes into
+-- the visit. Both are patient data and are stored encrypted.
+--
+-- Only AUDIO attachments are transcribed: transcription_status is NULL for
+-- photos and files.
+
+ALTER TABLE visit_attachments
+    ADD COLUMN IF NOT EXISTS transcription_status VARCHAR(20) CHECK (
+        transcription_status IN ('PENDING', 'PROCESSING', 'COMPLETED', 'FAILED', 'SKIPPED')
+    ),
+    -- 🔒 Encrypted transcript
+    ADD COLUMN IF NOT EXISTS transcript TEXT,
+    -- 🔒 Encrypted JSON: {"subjective": ..., "objective": ...}
+    ADD COLUMN IF NOT EXISTS transcript_proposal TEXT,
+    ADD COLUMN IF NOT EXISTS transcription_attempts INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS transcription_error TEXT,
+    ADD COLUMN IF NOT EXISTS transcription_started_at TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS transcription_completed_at TIMESTAMPTZ,
+    ADD CONSTRAINT visit_attachments_transcription_kind CHECK (
+        transcription_status IS NULL OR kind = 'AUDIO'
+    );
+
+-- Memos uploaded before this migration are transcribed too
+UPDATE visit_attachments
+SET transcription_status = 'PENDING'
+WHERE kind = 'AUDIO' AND transcription_status IS NULL AND deleted_at IS NULL;
+
+-- Queue of the transcription job
+CREATE INDEX IF NOT EXISTS idx_visit_attachments_transcription_queue
+    ON visit_attachments (created_at)
+    WHERE transcription_status IN ('PENDING', 'PROCESSING') AND deleted_at IS NULL;
+
+COMMENT ON COLUMN visit_attachments.transcript IS '🔒 ENCRYPTED - Transcript of the audio memo';
+COMMENT ON COLUMN visit_attachments.transcript_proposal IS '🔒 ENCRYPTED - Transcript split into proposed subjective/objective text';
+
+-- A memo can be transcribed after its visit is signed: updates touching
+-- only the transcription columns are allowed on any visit
+CREATE OR REPLACE FUNCTION check_visit_attachment_target()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'UPDATE'
+       AND OLD.patient_id::TEXT = current_setting('app.merge_patient_id', TRUE)
+       AND NEW.patient_id::TEXT = current_setting('app.merge_into_patient_id', TRUE)
+       AND (to_jsonb(NEW) - 'patient_id') = (to_jsonb(OLD) - 'patient_id') THEN
+        RETURN NEW;
+    END IF;
+
+    IF TG_OP = 'UPDATE'
+       AND (to_jsonb(NEW) - ARRAY['transcription_status', 'transcript', 'transcript_proposal',
+                                  'transcription_attempts', 'transcription_error',
+                                  'transcription_started_at', 'transcription_completed_at'])
+         = (to_jsonb(OLD) - ARRAY['transcription_status', 'transcript', 'transcript_proposal',
+                                  'transcription_attempts', 'transcription_error',
+                                  'transcription_started_at', 'transcription_completed_at']) THEN
+        RETURN NEW;
+    END IF;
+
+    IF NOT EXISTS (
+        SELECT 1 FROM visits
+        WHERE id = NEW.visit_id AND visit_date = NEW.visit_date AND status = 'DRAFT'
+    ) THEN
+        RAISE EXCEPTION 'Attachments can only be changed on draft visits';
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,184 @@
+-- Migration: Clinical summary template
+-- Date: 2026-04-16
+--
+-- Built-in template of GET /api/v1/visits/:id/export/pdf (one visit) and
+-- GET /api/v1/patients/:id/export/pdf (the full chart). DocumentService
+-- fills the `summary` variables from the chart: demographics, and per visit
+-- vitals, SOAP notes, diagnoses and prescriptions; the full chart also has
+-- the active problems and current medications. Not the default
+-- VISIT_SUMMARY template, so documents generated from a template the user
+-- picks are unaffected.
+--
+-- Uses ON CONFLICT (template_key) DO NOTHING for idempotent re-runs.
+
+ALTER TABLE document_templates DISABLE ROW LEVEL SECURITY;
+
+INSERT INTO document_templates (
+    template_key, template_name, description, document_type,
+    template_html, template_variables, header_html, footer_html, css_styles,
+    page_size, page_orientation, margin_top_mm, margin_bottom_mm, margin_left_mm, margin_right_mm,
+    is_active, is_default, language
+) VALUES (
+    'clinical_summary_it',
+    'Riepilogo Clinico',
+    'Riepilogo clinico strutturato di una visita o della cartella completa (anagrafica, parametri, SOAP, diagnosi, prescrizioni)',
+    'VISIT_SUMMARY',
+    E'<div class="clinical-summary">
+    <h1 class="title">{% if summary.scope == "CHART" %}CARTELLA CLINICA{% else %}RIEPILOGO VISITA{% endif %}</h1>
+
+    <table class="info-table">
+        <tr>
+            <td><strong>Paziente:</strong></td>
+            <td>{{patient.full_name}}</td>
+            <td><strong>Data di nascita:</strong></td>
+            <td>{{patient.date_of_birth}}</td>
+        </tr>
+        <tr>
+            <td><strong>Codice Fiscale:</strong></td>
+            <td>{{patient.fiscal_code}}</td>
+            <td><strong>N. cartella:</strong></td>
+            <td>{{summary.demographics.medical_record_number}}</td>
+        </tr>
+        <tr>
+            <td><strong>Sesso:</strong></td>
+            <td>{{patient.gender}}</td>
+            <td><strong>Gruppo sanguigno:</strong></td>
+            <td>{% if summary.demographics.blood_type %}{{summary.demographics.blood_type}}{% else %}-{% endif %}</td>
+        </tr>
+    </table>
+
+    <p><strong>Allergie:</strong> {% if summary.demographics.allergies %}{{summary.demographics.allergies | join(", ")}}{% else %}nessuna allergia nota{% endif %}</p>
+    {% if summary.demographics.chronic_conditions %}
+    <p><strong>Patologie croniche:</strong> {{summary.demographics.chronic_conditions | join(", ")}}</p>
+    {% endif %}
+
+    {% if summary.scope == "CHART" %}
+    <h2>Problemi Attivi</h2>
+    {% if summary.problems %}
+    <ul>
+    {% for problem in summary.problems %}
+        <li>{{problem.code}} - {{problem.description}} (dal {{problem.since}})</li>
+    {% endfor %}
+    </ul>
+    {% else %}
+    <p>Nessun problema attivo</p>
+    {% endif %}
+
+    <h2>Terapia in Corso</h2>
+    {% if summary.medications %}
+    <ul>
+    {% for rx in summary.medications %}
+        <li>{{rx.name}}{% if rx.generic_name %} ({{rx.generic_name}}){% endif %} {{rx.dosage}} - {{rx.frequency}}</li>
+    {% endfor %}
+    </ul>
+    {% else %}
+    <p>Nessuna terapia in corso</p>
+    {% endif %}
+
+    <h2>Visite</h2>
+    {% endif %}
+
+    {% for visit in summary.visits %}
+    {% if not loop.first %}<div class="page-break"></div>{% endif %}
+    <div class="visit">
+        <h3>{{visit.date}} - {{visit.type}}{% if visit.provider %} ({{visit.provider}}){% endif %}</h3>
+        {% if visit.status == "DRAFT" %}<p class="draft">Bozza non firmata</p>{% endif %}
+        {% if visit.chief_complaint %}<p><strong>Motivo:</strong> {{visit.chief_complaint}}</p>{% endif %}
+
+        {% if visit.vitals %}
+        <h4>Parametri Vitali</h4>
+        <p>
+        {% if visit.vitals.blood_pressure_systolic %}PA {{visit.vitals.blood_pressure_systolic}}/{{visit.vitals.blood_pressure_diastolic}} mmHg; {% endif %}
+        {% if visit.vitals.heart_rate %}FC {{visit.vitals.heart_rate}} bpm; {% endif %}
+        {% if visit.vitals.respiratory_rate %}FR {{visit.vitals.respiratory_rate}} atti/min; {% endif %}
+        {% if visit.vitals.temperature_celsius %}T {{visit.vitals.temperature_celsius}} °C; {% endif %}
+        {% if visit.vitals.oxygen_saturation %}SpO2 {{visit.vitals.oxygen_saturation}}%; {% endif %}
+        {% if visit.vitals.weight_kg %}Peso {{visit.vitals.weight_kg}} kg; {% endif %}
+        {% if visit.vitals.height_cm %}Altezza {{visit.vitals.height_cm}} cm; {% endif %}
+        {% if visit.vitals.bmi %}BMI {{visit.vitals.bmi | round(1)}}{% endif %}
+        </p>
+        {% endif %}
+
+        {% if visit.subjective %}<h4>Anamnesi (S)</h4><p>{{visit.subjective}}</p>{% endif %}
+        {% if visit.objective %}<h4>Esame Obiettivo (O)</h4><p>{{visit.objective}}</p>{% endif %}
+        {% if visit.assessment %}<h4>Valutazione (A)</h4><p>{{visit.assessment}}</p>{% endif %}
+        {% if visit.plan %}<h4>Piano (P)</h4><p>{{visit.plan}}</p>{% endif %}
+
+        <h4>Diagnosi</h4>
+        {% if visit.diagnoses %}
+        <ul>
+        {% for diagnosis in visit.diagnoses %}
+            <li><strong>{{diagnosis.code}}</strong> - {{diagnosis.description}}{% if diagnosis.primary %} (principale){% endif %}</li>
+        {% endfor %}
+        </ul>
+        {% else %}
+        <p>Nessuna diagnosi registrata</p>
+        {% endif %}
+
+        <h4>Prescrizioni</h4>
+        {% if visit.prescriptions %}
+        <table class="data-table">
+            <thead><tr><th>Farmaco</th><th>Dosaggio</th><th>Posologia</th><th>Durata</th></tr></thead>
+            <tbody>
+            {% for rx in visit.prescriptions %}
+                <tr>
+                    <td>{{rx.name}}{% if rx.generic_name %} ({{rx.generic_name}}){% endif %}</td>
+                    <td>{{rx.dosage}}</td>
+                    <td>{{rx.frequency}}{% if rx.instructions %} - {{rx.instructions}}{% endif %}</td>
+                    <td>{% if rx.duration %}{{rx.duration}}{% else %}-{% endif %}</td>
+                </tr>
+            {% endfor %}
+            </tbody>
+        </table>
+        {% else %}
+        <p>Nessuna prescrizione</p>
+        {% endif %}
+
+        {% if visit.follow_up_date %}
+        <p><strong>Controllo:</strong> {{visit.follow_up_date}}{% if visit.follow_up_notes %} - {{visit.follow_up_notes}}{% endif %}</p>
+        {% endif %}
+    </div>
+    {% else %}
+    <p>Nessuna visita registrata</p>
+    {% endfor %}
+
+    <div class="footer-section">
+        <div class="date-location">
+            <p>{{clinic.city}}, {{document.date}}</p>
+        </div>
+        <div class="signature">
+            <p class="signature-line">_________________________</p>
+            <p>Dr. {{provider.full_name}}</p>
+        </div>
+    </div>
+</div>',
+    '{"required": ["patient", "provider", "clinic", "summary", "document"], "patient": ["full_name", "date_of_birth", "fiscal_code", "gender"], "provider": ["full_name"], "clinic": ["city"], "summary": ["scope", "demographics", "problems", "medications", "visits"], "document": ["date"]}',
+    E'<div class="header">
+    <div class="clinic-info">
+        <h2>{{clinic.name}}</h2>
+        <p>{{clinic.address}} - {{clinic.city}} ({{clinic.province}})</p>
+        <p>Tel: {{clinic.phone}} | Email: {{clinic.email}}</p>
+    </div>
+</div>',
+    E'<div class="footer">
+    <p>Documento riservato - contiene dati sanitari personali</p>
+</div>',
+    E'.clinical-summary { font-family: Arial, sans-serif; font-size: 11pt; }
+.title { text-align: center; margin-bottom: 20px; }
+.info-table { width: 100%; margin-bottom: 15px; }
+.data-table { width: 100%; border-collapse: collapse; margin-bottom: 15px; }
+.data-table th, .data-table td { border: 1px solid #ccc; padding: 4px; text-align: left; }
+.visit { margin-bottom: 15px; }
+.draft { font-style: italic; color: #c0392b; }
+.page-break { page-break-before: always; }
+.footer-section { display: flex; justify-content: space-between; margin-top: 30px; }
+.signature { text-align: center; }
+.signature-line { margin: 20px 0 5px 0; }
+.header { border-bottom: 2px solid #333; padding-bottom: 10px; margin-bottom: 15px; }
+.clinic-info { text-align: center; }
+.footer { text-align: center; border-top: 1px solid #ccc; padding-top: 10px; font-size: 9pt; }',
+    'A4', 'PORTRAIT', 20, 15, 20, 20,
+    true, false, 'it'
+) ON CONFLICT (template_key) DO NOTHING;
+
+ALTER TABLE document_templates ENABLE ROW LEVEL SECURITY;
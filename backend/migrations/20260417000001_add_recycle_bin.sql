@@ -0,0 +1,116 @@
+-- Migration: Recycle Bin
+-- Date: 2026-04-17
+--
+-- Deleting a patient, a draft visit or a prescription moves it to the
+-- recycle bin instead of removing it: deleted_at/deleted_by are set and the
+-- row is hidden by RLS from every query. GET /api/v1/trash lists the bin and
+-- POST /api/v1/trash/:entity_type/:id/restore brings a record back. The
+-- RECYCLE_BIN retention rule (run by ADMIN or the retention job) purges
+-- records deleted before its cutoff; a patient is only purged once no
+-- clinical record refers to it.
+--
+-- Transaction-local settings, set by db::rls:
+-- - app.include_trash = 'on' shows every record in the bin (listing, purge)
+-- - app.trash_record_id names the record being deleted or restored
+
+-- ====================
+-- DELETION COLUMNS
+-- ====================
+
+ALTER TABLE patients
+    ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS deleted_by UUID REFERENCES users(id);
+
+ALTER TABLE visits
+    ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS deleted_by UUID REFERENCES users(id);
+
+ALTER TABLE prescriptions
+    ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS deleted_by UUID REFERENCES users(id);
+
+CREATE INDEX IF NOT EXISTS idx_patients_deleted_at
+    ON patients (deleted_at)
+    WHERE deleted_at IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_visits_deleted_at
+    ON visits (deleted_at)
+    WHERE deleted_at IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_prescriptions_deleted_at
+    ON prescriptions (deleted_at)
+    WHERE deleted_at IS NOT NULL;
+
+COMMENT ON COLUMN patients.deleted_at IS 'Moved to the recycle bin; hidden until restored or purged';
+COMMENT ON COLUMN visits.deleted_at IS 'Moved to the recycle bin; hidden until restored or purged';
+COMMENT ON COLUMN prescriptions.deleted_at IS 'Moved to the recycle bin; hidden until restored or purged';
+
+-- ====================
+-- RLS
+-- ====================
+
+-- Restrictive: on top of the existing policies, rows in the bin are only
+-- visible when the transaction asks for them
+DROP POLICY IF EXISTS patients_trash_visibility_policy ON patients;
+CREATE POLICY patients_trash_visibility_policy ON patients
+    AS RESTRICTIVE
+    FOR SELECT
+    USING (
+        deleted_at IS NULL
+        OR current_setting('app.include_trash', TRUE) = 'on'
+        OR id::TEXT = current_setting('app.trash_record_id', TRUE)
+    );
+
+DROP POLICY IF EXISTS visits_trash_visibility_policy ON visits;
+CREATE POLICY visits_trash_visibility_policy ON visits
+    AS RESTRICTIVE
+    FOR SELECT
+    USING (
+        deleted_at IS NULL
+        OR current_setting('app.include_trash', TRUE) = 'on'
+        OR id::TEXT = current_setting('app.trash_record_id', TRUE)
+    );
+
+DROP POLICY IF EXISTS prescriptions_trash_visibility_policy ON prescriptions;
+CREATE POLICY prescriptions_trash_visibility_policy ON prescriptions
+    AS RESTRICTIVE
+    FOR SELECT
+    USING (
+        deleted_at IS NULL
+        OR current_setting('app.include_trash', TRUE) = 'on'
+        OR id::TEXT = current_setting('app.trash_record_id', TRUE)
+    );
+
+-- Admins may delete and restore another provider's visit or prescription,
+-- named by app.trash_record_id (doctors already update their own)
+DROP POLICY IF EXISTS visits_trash_update_policy ON visits;
+CREATE POLICY visits_trash_update_policy ON visits
+    FOR UPDATE
+    USING (is_admin() AND id::TEXT = current_setting('app.trash_record_id', TRUE))
+    WITH CHECK (is_admin() AND id::TEXT = current_setting('app.trash_record_id', TRUE));
+
+DROP POLICY IF EXISTS prescriptions_trash_update_policy ON prescriptions;
+CREATE POLICY prescriptions_trash_update_policy ON prescriptions
+    FOR UPDATE
+    USING (is_admin() AND id::TEXT = current_setting('app.trash_record_id', TRUE))
+    WITH CHECK (is_admin() AND id::TEXT = current_setting('app.trash_record_id', TRUE));
+
+-- Purge (the delete policies are already ADMIN-only for patients and
+-- prescriptions, ADMIN or the owner of a draft for visits)
+GRANT DELETE ON patients, visits TO mpms_user;
+
+-- ====================
+-- RETENTION RULE
+-- ====================
+
+ALTER TABLE retention_rules
+    DROP CONSTRAINT IF EXISTS retention_rules_entity_type_check;
+ALTER TABLE retention_rules
+    ADD CONSTRAINT retention_rules_entity_type_check CHECK (
+        entity_type IN (
+            'GENERATED_DOCUMENTS', 'AUDIT_LOGS', 'NOTIFICATIONS', 'PATIENT_DATA_EXPORTS',
+            'RECYCLE_BIN'
+        )
+    );
+
+INSERT INTO retention_rules (entity_type, retention_days, description) VALUES
+    ('RECYCLE_BIN', 30, 'Deleted patients, visits and prescriptions, 30 days after deletion')
+ON CONFLICT (entity_type) DO NOTHING;
@@ -0,0 +1,58 @@
+-- Migration: Visit note search index
+-- Date: 2026-04-18
+--
+-- Full-text search over the narrative fields of visits (chief complaint,
+-- history, SOAP notes, physical exam, clinical and follow-up notes). The
+-- notes are encrypted, so PostgreSQL cannot parse them: the application
+-- splits each visit into normalized words on write and stores a tsvector of
+-- keyed hashes of those words (HMAC with a key derived from ENCRYPTION_KEY),
+-- weighted by field and keeping word positions. A search hashes the query
+-- words the same way. GET /api/v1/visits/search ranks the matches, decrypts
+-- them and highlights the words found.
+--
+-- The index is a separate table because signed and locked visits cannot be
+-- updated. Visits saved before this migration are indexed by a background
+-- job (any visit without a row here).
+
+-- ====================
+-- TABLE
+-- ====================
+
+CREATE TABLE IF NOT EXISTS visit_search_index (
+    visit_id UUID PRIMARY KEY,
+    visit_date DATE NOT NULL,  -- Required for partition key in visits table
+    search_vector TSVECTOR NOT NULL,
+    indexed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    FOREIGN KEY (visit_id, visit_date) REFERENCES visits(id, visit_date) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_visit_search_index_vector
+    ON visit_search_index USING GIN (search_vector);
+
+COMMENT ON TABLE visit_search_index IS 'Full-text index of visit notes; lexemes are keyed hashes of the words, never plaintext';
+COMMENT ON COLUMN visit_search_index.search_vector IS 'Hashed words with positions; weight A chief complaint and assessment, B history, SOAP and exam, C plan and notes';
+
+-- ====================
+-- RLS
+-- ====================
+-- Written by whoever saves the visit (doctors, nurses) and by the indexing
+-- job (ADMIN). Searches join visits, whose policies decide what is found.
+
+ALTER TABLE visit_search_index ENABLE ROW LEVEL SECURITY;
+ALTER TABLE visit_search_index FORCE ROW LEVEL SECURITY;
+
+CREATE POLICY visit_search_index_select_policy ON visit_search_index
+    FOR SELECT
+    USING (is_doctor() OR is_nurse());
+
+CREATE POLICY visit_search_index_insert_policy ON visit_search_index
+    FOR INSERT
+    WITH CHECK (is_doctor() OR is_nurse());
+
+CREATE POLICY visit_search_index_update_policy ON visit_search_index
+    FOR UPDATE
+    USING (is_doctor() OR is_nurse())
+    WITH CHECK (is_doctor() OR is_nurse());
+
+GRANT SELECT, INSERT, UPDATE ON visit_search_index TO mpms_user;
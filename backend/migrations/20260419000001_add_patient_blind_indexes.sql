@@ -0,0 +1,51 @@
+-- Migration: Patient blind indexes
+-- Date: 2026-04-19
+--
+-- Patient names, fiscal codes and contacts are encrypted with a random
+-- nonce, so equal values never produce equal ciphertexts and every lookup
+-- used to decrypt the whole table. Blind indexes are keyed hashes (HMAC
+-- with a key derived from ENCRYPTION_KEY) of the normalized values, written
+-- by the application next to the ciphertext: exact matches compare hashes,
+-- and last name prefixes are looked up among the hashes of every prefix
+-- of the name (2 to 12 characters).
+--
+-- Patients saved before this migration are indexed by a background job
+-- (any patient without last_name_bidx that is neither anonymized nor
+-- merged); the update refreshes their updated_at, and with it their ETag,
+-- once. Erasure clears the indexes with the values they were computed from.
+
+-- ====================
+-- COLUMNS
+-- ====================
+
+ALTER TABLE patients
+    ADD COLUMN IF NOT EXISTS fiscal_code_bidx TEXT,
+    ADD COLUMN IF NOT EXISTS last_name_bidx TEXT,
+    ADD COLUMN IF NOT EXISTS last_name_prefix_bidx TEXT[],
+    ADD COLUMN IF NOT EXISTS phone_bidx TEXT[],
+    ADD COLUMN IF NOT EXISTS email_bidx TEXT;
+
+COMMENT ON COLUMN patients.fiscal_code_bidx IS 'Keyed hash of the fiscal code, uppercase without spaces';
+COMMENT ON COLUMN patients.last_name_bidx IS 'Keyed hash of the last name, letters and digits only, lowercase, accents folded';
+COMMENT ON COLUMN patients.last_name_prefix_bidx IS 'Keyed hashes of the prefixes (2 to 12 characters) of the normalized last name';
+COMMENT ON COLUMN patients.phone_bidx IS 'Keyed hashes of the primary and secondary phone, digits only without the +39 prefix';
+COMMENT ON COLUMN patients.email_bidx IS 'Keyed hash of the email, trimmed and lowercase';
+
+-- ====================
+-- INDEXES
+-- ====================
+
+CREATE INDEX IF NOT EXISTS idx_patients_fiscal_code_bidx
+    ON patients(fiscal_code_bidx) WHERE fiscal_code_bidx IS NOT NULL;
+
+CREATE INDEX IF NOT EXISTS idx_patients_last_name_bidx
+    ON patients(last_name_bidx);
+
+CREATE INDEX IF NOT EXISTS idx_patients_last_name_prefix_bidx
+    ON patients USING GIN (last_name_prefix_bidx);
+
+CREATE INDEX IF NOT EXISTS idx_patients_phone_bidx
+    ON patients USING GIN (phone_bidx);
+
+CREATE INDEX IF NOT EXISTS idx_patients_email_bidx
+    ON patients(email_bidx) WHERE email_bidx IS NOT NULL;
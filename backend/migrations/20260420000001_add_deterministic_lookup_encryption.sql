@@ -0,0 +1,28 @@
+-- Migration: Deterministic encryption of lookup fields
+-- Date: 2026-04-20
+--
+-- Patient fiscal codes and insurance policy numbers are now encrypted with
+-- AES-SIV instead of AES-GCM with a random nonce: the same value always
+-- gives the same ciphertext (`siv:<field>:<base64>`), so PostgreSQL can
+-- enforce uniqueness and join or compare on the ciphertext. The existing
+-- indexes on the two columns now find equal values.
+--
+-- Values written before this migration are re-encrypted by a background job
+-- (any value not starting with `siv:`). A fiscal code shared by two active
+-- patients is left as it was until they are merged, and the job warns about
+-- it. Re-encrypting a patient refreshes its updated_at, and with it its
+-- ETag, once.
+
+-- ====================
+-- CONSTRAINTS
+-- ====================
+-- One active patient per fiscal code. Merged records and records in the
+-- recycle bin do not count; restoring a patient whose fiscal code has been
+-- taken since fails with a conflict.
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_patients_fiscal_code_unique
+    ON patients(fiscal_code)
+    WHERE fiscal_code LIKE 'siv:%' AND merged_at IS NULL AND deleted_at IS NULL;
+
+COMMENT ON COLUMN patients.fiscal_code IS '🔒 ENCRYPTED (deterministic, AES-SIV) - Italian tax code (Codice Fiscale)';
+COMMENT ON COLUMN patient_insurance.policy_number IS '🔒 ENCRYPTED (deterministic, AES-SIV) - Insurance policy number';
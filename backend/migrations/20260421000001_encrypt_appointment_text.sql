@@ -0,0 +1,31 @@
+-- Migration: Encryption of appointment free text
+-- Date: 2026-04-21
+--
+-- The reason, notes and cancellation reason of appointments were the only
+-- free text about a patient's health stored in plaintext. The application
+-- now encrypts them like visit notes (AES-256-GCM with ENCRYPTION_KEY) and
+-- decrypts them when building API responses.
+--
+-- text_encrypted tells which rows already hold ciphertext. Appointments
+-- saved before this migration are encrypted by a background job (any row
+-- where it is false), or as soon as they are updated or cancelled; either
+-- refreshes their updated_at, and with it their ETag, once.
+
+-- ====================
+-- COLUMNS
+-- ====================
+
+ALTER TABLE appointments
+    ADD COLUMN IF NOT EXISTS text_encrypted BOOLEAN NOT NULL DEFAULT FALSE;
+
+COMMENT ON COLUMN appointments.text_encrypted IS 'Whether reason, notes and cancellation_reason hold ciphertext';
+COMMENT ON COLUMN appointments.reason IS '🔒 ENCRYPTED - Reason for the appointment';
+COMMENT ON COLUMN appointments.notes IS '🔒 ENCRYPTED - Appointment notes';
+COMMENT ON COLUMN appointments.cancellation_reason IS '🔒 ENCRYPTED - Cancellation reason';
+
+-- ====================
+-- INDEXES
+-- ====================
+
+CREATE INDEX IF NOT EXISTS idx_appointments_text_unencrypted
+    ON appointments(created_at) WHERE NOT text_encrypted;
@@ -0,0 +1,35 @@
+-- Migration: Patient trigram blind index for autocomplete
+-- Date: 2026-04-22
+--
+-- Patient autocomplete (GET /patients/autocomplete) matches part of a name,
+-- fiscal code or phone number. pg_trgm cannot help on the encrypted
+-- columns: the trigram indexes created with the full-text search index
+-- ciphertext, which shares no trigrams with the plaintext. The application
+-- now computes the trigrams itself (runs of three characters of the
+-- normalized first and last name, fiscal code and phones), stores keyed
+-- hashes of them, and a patient matches when its hashes include every hash
+-- of the query. Candidates are then decrypted and checked.
+--
+-- Patients saved before this migration are indexed by the blind index
+-- backfill job; the update refreshes their updated_at, and with it their
+-- ETag, once. Until then autocomplete does not find them.
+
+-- ====================
+-- COLUMNS
+-- ====================
+
+ALTER TABLE patients
+    ADD COLUMN IF NOT EXISTS search_trigram_bidx TEXT[];
+
+COMMENT ON COLUMN patients.search_trigram_bidx IS 'Keyed hashes (64 bits) of the trigrams of the normalized names, fiscal code and phones';
+
+-- ====================
+-- INDEXES
+-- ====================
+
+CREATE INDEX IF NOT EXISTS idx_patients_search_trigram_bidx
+    ON patients USING GIN (search_trigram_bidx);
+
+-- Trigram indexes over ciphertext never match a search term
+DROP INDEX IF EXISTS idx_patients_name_trgm;
+DROP INDEX IF EXISTS idx_patients_fiscal_code_trgm;
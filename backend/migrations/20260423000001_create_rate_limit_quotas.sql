@@ -0,0 +1,28 @@
+-- Migration: Per-user rate limit quotas
+-- Date: 2026-04-23
+--
+-- API requests are limited per user (300/minute, bulk operations 10/minute)
+-- and per client IP before login (middleware/rate_limit.rs). Administrators
+-- can give a user other limits at runtime, e.g. an account used by an
+-- integration that polls the schedule. Each instance caches the quotas and
+-- reloads them every minute.
+
+-- ====================
+-- QUOTAS
+-- ====================
+
+CREATE TABLE IF NOT EXISTS rate_limit_quotas (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    requests_per_minute INTEGER NOT NULL CHECK (requests_per_minute BETWEEN 1 AND 100000),
+    bulk_requests_per_minute INTEGER NOT NULL CHECK (bulk_requests_per_minute BETWEEN 1 AND 10000),
+    reason TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_by UUID REFERENCES users(id)
+);
+
+COMMENT ON TABLE rate_limit_quotas IS 'Request limits per user replacing the defaults of the rate limiter';
+COMMENT ON COLUMN rate_limit_quotas.requests_per_minute IS 'Requests allowed per one-minute window';
+COMMENT ON COLUMN rate_limit_quotas.bulk_requests_per_minute IS 'Bulk, batch, export and import requests allowed per one-minute window';
+
+GRANT SELECT, INSERT, UPDATE, DELETE ON rate_limit_quotas TO mpms_user;
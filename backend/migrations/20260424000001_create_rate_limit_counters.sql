@@ -0,0 +1,27 @@
+-- Migration: Shared rate limit counters
+-- Date: 2026-04-24
+--
+-- With RATE_LIMIT_BACKEND=postgres all backend replicas count requests in
+-- this table instead of in memory, so a client gets the same limit whichever
+-- instance serves it. One row per client key and one-minute window; rows of
+-- past windows are deleted every minute. The table is UNLOGGED: counters are
+-- short-lived and losing them on a crash only resets the current window.
+
+-- ====================
+-- COUNTERS
+-- ====================
+
+CREATE UNLOGGED TABLE IF NOT EXISTS rate_limit_counters (
+    key TEXT NOT NULL,
+    window_index BIGINT NOT NULL,
+    count INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (key, window_index)
+);
+
+CREATE INDEX IF NOT EXISTS idx_rate_limit_counters_window ON rate_limit_counters(window_index);
+
+COMMENT ON TABLE rate_limit_counters IS 'Requests per client key and window, shared by all instances (RATE_LIMIT_BACKEND=postgres)';
+COMMENT ON COLUMN rate_limit_counters.key IS 'user:<id> or ip:<address>, prefixed with bulk: for bulk operations';
+COMMENT ON COLUMN rate_limit_counters.window_index IS 'Unix time divided by the window length (60 seconds)';
+
+GRANT SELECT, INSERT, UPDATE, DELETE ON rate_limit_counters TO mpms_user;
@@ -0,0 +1,115 @@
+-- Migration: Materialized views for report statistics
+-- Date: 2026-04-25
+--
+-- The heaviest report aggregations (appointment utilization, monthly patient
+-- registrations, diagnosis trends) are pre-computed into daily rollups that a
+-- background task refreshes every `report_views_refresh_minutes`. ReportService
+-- aggregates the rollups further for the requested period, and falls back to
+-- the same rollups computed live when the views are stale or the caller asks
+-- for live data.
+--
+-- The view definitions must stay in sync with the live rollups in
+-- src/services/report_service.rs. The views are refreshed as the system
+-- administrator, so they see the same rows as a report run by an ADMIN or
+-- DOCTOR (the only roles allowed to read reports).
+
+-- ====================
+-- APPOINTMENTS
+-- ====================
+
+CREATE MATERIALIZED VIEW IF NOT EXISTS report_appointment_stats AS
+SELECT
+    scheduled_start::DATE AS appointment_date,
+    EXTRACT(HOUR FROM scheduled_start)::INTEGER AS hour,
+    provider_id,
+    type::TEXT AS appointment_type,
+    status::TEXT AS status,
+    COUNT(*)::BIGINT AS count
+FROM appointments
+GROUP BY 1, 2, 3, 4, 5
+WITH NO DATA;
+
+-- Unique index required by REFRESH MATERIALIZED VIEW CONCURRENTLY
+CREATE UNIQUE INDEX IF NOT EXISTS idx_report_appointment_stats_key
+    ON report_appointment_stats (appointment_date, hour, provider_id, appointment_type, status);
+
+COMMENT ON MATERIALIZED VIEW report_appointment_stats IS 'Appointments per day, hour, provider, type and status (appointment utilization report)';
+
+-- ====================
+-- PATIENT REGISTRATIONS
+-- ====================
+
+CREATE MATERIALIZED VIEW IF NOT EXISTS report_patient_registrations AS
+SELECT
+    created_at::DATE AS registration_date,
+    COUNT(*)::BIGINT AS count
+FROM patients
+GROUP BY 1
+WITH NO DATA;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_report_patient_registrations_key
+    ON report_patient_registrations (registration_date);
+
+COMMENT ON MATERIALIZED VIEW report_patient_registrations IS 'Patients registered per day (patient statistics report)';
+
+-- ====================
+-- DIAGNOSES
+-- ====================
+
+CREATE MATERIALIZED VIEW IF NOT EXISTS report_diagnosis_stats AS
+SELECT
+    v.visit_date,
+    vd.icd10_code,
+    vd.icd10_description,
+    COUNT(*)::BIGINT AS count
+FROM visit_diagnoses vd
+JOIN visits v ON vd.visit_id = v.id
+GROUP BY 1, 2, 3
+WITH NO DATA;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_report_diagnosis_stats_key
+    ON report_diagnosis_stats (visit_date, icd10_code, icd10_description);
+
+COMMENT ON MATERIALIZED VIEW report_diagnosis_stats IS 'Diagnoses per visit date and ICD-10 code (diagnosis trends report)';
+
+GRANT SELECT ON report_appointment_stats, report_patient_registrations, report_diagnosis_stats TO mpms_user;
+
+-- ====================
+-- REFRESH STATE
+-- ====================
+
+-- A view is used by reports only while stale_after is in the future, so
+-- views that were never refreshed (or whose refresh stopped) are bypassed.
+CREATE TABLE IF NOT EXISTS report_view_refreshes (
+    view_name VARCHAR(100) PRIMARY KEY,
+    refreshed_at TIMESTAMPTZ NOT NULL,
+    duration_ms BIGINT NOT NULL,
+    stale_after TIMESTAMPTZ NOT NULL
+);
+
+COMMENT ON TABLE report_view_refreshes IS 'Last successful refresh of each report materialized view';
+COMMENT ON COLUMN report_view_refreshes.stale_after IS 'When the view stops being used by reports unless refreshed again (two refresh intervals)';
+
+GRANT SELECT, INSERT, UPDATE, DELETE ON report_view_refreshes TO mpms_user;
+
+-- ====================
+-- SETTINGS
+-- ====================
+
+INSERT INTO system_settings (
+    setting_key, setting_group, setting_name, setting_value,
+    value_type, description, default_value, is_public, is_encrypted, is_readonly
+) VALUES
+(
+    'report_views_refresh_minutes',
+    'system',
+    'Report Views Refresh Interval (minutes)',
+    '15',
+    'INTEGER',
+    'Minutes between refreshes of the materialized views behind the appointment, patient and diagnosis reports. 0 disables the refresh; reports are then computed live.',
+    '15',
+    false,
+    false,
+    false
+)
+ON CONFLICT (setting_key) DO NOTHING;
@@ -0,0 +1,49 @@
+-- Migration: Scheduled report subscriptions
+-- Date: 2026-04-26
+--
+-- A subscription emails a report (PDF, Excel, CSV or JSON attachment) to a
+-- list of recipients every week, month or quarter, covering the last
+-- complete period. The report is generated with the permissions of the user
+-- who created the subscription (services/report_subscription_service.rs).
+-- Deliveries are due from next_run_at; each instance claims due rows with
+-- FOR UPDATE SKIP LOCKED, so a delivery is sent once with several replicas.
+
+-- ====================
+-- SUBSCRIPTIONS
+-- ====================
+
+CREATE TABLE IF NOT EXISTS report_subscriptions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(200) NOT NULL,
+    report_type VARCHAR(50) NOT NULL CHECK (
+        report_type IN (
+            'appointment_utilization', 'patient_statistics', 'diagnosis_trends',
+            'provider_productivity', 'revenue', 'dashboard'
+        )
+    ),
+    -- Report filters, e.g. {"provider_id": "..."}
+    filters JSONB NOT NULL DEFAULT '{}'::jsonb,
+    format VARCHAR(10) NOT NULL CHECK (format IN ('json', 'csv', 'pdf', 'excel')),
+    cadence VARCHAR(20) NOT NULL CHECK (cadence IN ('WEEKLY', 'MONTHLY', 'QUARTERLY')),
+    recipients TEXT[] NOT NULL CHECK (cardinality(recipients) BETWEEN 1 AND 20),
+    is_active BOOLEAN NOT NULL DEFAULT TRUE,
+    next_run_at TIMESTAMPTZ NOT NULL,
+    last_run_at TIMESTAMPTZ,
+    last_status VARCHAR(20) CHECK (last_status IN ('SENT', 'PARTIALLY_SENT', 'FAILED')),
+    last_error TEXT,
+    created_by UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_report_subscriptions_due
+    ON report_subscriptions (next_run_at)
+    WHERE is_active;
+CREATE INDEX IF NOT EXISTS idx_report_subscriptions_created_by
+    ON report_subscriptions (created_by);
+
+COMMENT ON TABLE report_subscriptions IS 'Reports emailed to recipients on a weekly, monthly or quarterly cadence';
+COMMENT ON COLUMN report_subscriptions.next_run_at IS 'When the next delivery is due (start of the next period)';
+COMMENT ON COLUMN report_subscriptions.created_by IS 'Owner; reports are generated with this user''s permissions';
+
+GRANT SELECT, INSERT, UPDATE, DELETE ON report_subscriptions TO mpms_user;
@@ -0,0 +1,32 @@
+-- Migration: Saved patient cohorts
+-- Date: 2026-04-27
+--
+-- A cohort selects patients by criteria (age range, diagnoses, active
+-- medications, last visit before a date). Only the criteria are saved: the
+-- patients are selected again each time the cohort is previewed or used for
+-- bulk document generation or a notification campaign
+-- (services/patient_cohort_service.rs). Ages and medication names are
+-- encrypted, so those criteria are applied in the application.
+
+-- ====================
+-- COHORTS
+-- ====================
+
+CREATE TABLE IF NOT EXISTS patient_cohorts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(200) NOT NULL,
+    description TEXT,
+    -- Cohort criteria, e.g. {"min_age": 65, "diagnosis_codes": ["E11"]}
+    criteria JSONB NOT NULL DEFAULT '{}'::jsonb,
+    created_by UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_patient_cohorts_created_by
+    ON patient_cohorts (created_by);
+
+COMMENT ON TABLE patient_cohorts IS 'Saved patient selection criteria for bulk documents and notification campaigns';
+COMMENT ON COLUMN patient_cohorts.criteria IS 'Selection criteria; the patients are selected again on every use';
+
+GRANT SELECT, INSERT, UPDATE, DELETE ON patient_cohorts TO mpms_user;
@@ -0,0 +1,126 @@
+-- Migration: Recall and follow-up campaigns
+-- Date: 2026-04-28
+--
+-- A recall rule selects patients overdue for follow-up: a population (the
+-- cohort criteria of patient_cohorts) without a lab order or result for some
+-- LOINC codes, or without a visit, for a number of months. Rules run on a
+-- cadence (services/recall_service.rs). Each run updates the rule's recall
+-- list in recall_entries: overdue patients get an OPEN entry and are queued
+-- a FOLLOW_UP_REMINDER, and entries whose patient is no longer overdue are
+-- RESOLVED. Patients can opt out of one rule or of every recall.
+
+-- ====================
+-- RULES
+-- ====================
+
+CREATE TABLE IF NOT EXISTS recall_rules (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(200) NOT NULL,
+    description TEXT,
+    -- e.g. {"diagnosis_codes": ["E11"], "lab_loinc_codes": ["4548-4"], "lab_within_months": 6}
+    criteria JSONB NOT NULL,
+    subject VARCHAR(255) NOT NULL,
+    message_body TEXT NOT NULL,
+    cadence VARCHAR(20) NOT NULL CHECK (cadence IN ('WEEKLY', 'MONTHLY', 'QUARTERLY')),
+    reminder_interval_days INT NOT NULL DEFAULT 30 CHECK (reminder_interval_days BETWEEN 1 AND 365),
+    max_reminders INT NOT NULL DEFAULT 3 CHECK (max_reminders BETWEEN 1 AND 10),
+    is_active BOOLEAN NOT NULL DEFAULT true,
+    next_run_at TIMESTAMPTZ NOT NULL,
+    last_run_at TIMESTAMPTZ,
+    -- Runs use this user's permissions (RLS)
+    created_by UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_recall_rules_due
+    ON recall_rules (next_run_at)
+    WHERE is_active;
+CREATE INDEX IF NOT EXISTS idx_recall_rules_created_by
+    ON recall_rules (created_by);
+
+COMMENT ON TABLE recall_rules IS 'Recall rules: overdue patients are listed and sent reminders on a cadence';
+COMMENT ON COLUMN recall_rules.criteria IS 'Population (cohort criteria) and lab/visit overdue conditions';
+
+-- ====================
+-- RUNS
+-- ====================
+
+CREATE TABLE IF NOT EXISTS recall_runs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    rule_id UUID NOT NULL REFERENCES recall_rules(id) ON DELETE CASCADE,
+    -- NULL for scheduled runs
+    triggered_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    run_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    overdue INT NOT NULL DEFAULT 0,
+    new_entries INT NOT NULL DEFAULT 0,
+    reminded INT NOT NULL DEFAULT 0,
+    skipped INT NOT NULL DEFAULT 0,
+    opted_out INT NOT NULL DEFAULT 0,
+    resolved INT NOT NULL DEFAULT 0,
+    unreadable_records INT NOT NULL DEFAULT 0,
+    error TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_recall_runs_rule
+    ON recall_runs (rule_id, run_at DESC);
+
+COMMENT ON TABLE recall_runs IS 'History of recall rule runs with their counts';
+
+-- ====================
+-- RECALL LISTS
+-- ====================
+
+CREATE TABLE IF NOT EXISTS recall_entries (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    rule_id UUID NOT NULL REFERENCES recall_rules(id) ON DELETE CASCADE,
+    patient_id UUID NOT NULL REFERENCES patients(id) ON DELETE CASCADE,
+    status VARCHAR(20) NOT NULL DEFAULT 'OPEN'
+        CHECK (status IN ('OPEN', 'RESOLVED', 'OPTED_OUT', 'CLOSED')),
+    reminders_sent INT NOT NULL DEFAULT 0,
+    last_reminded_at TIMESTAMPTZ,
+    last_notification_id UUID REFERENCES notification_queue(id) ON DELETE SET NULL,
+    last_skip_reason VARCHAR(255),
+    opened_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    closed_at TIMESTAMPTZ,
+
+    CONSTRAINT recall_entries_closed CHECK ((status = 'OPEN') = (closed_at IS NULL))
+);
+
+-- A patient is on a rule's list at most once at a time
+CREATE UNIQUE INDEX IF NOT EXISTS idx_recall_entries_open
+    ON recall_entries (rule_id, patient_id)
+    WHERE status = 'OPEN';
+CREATE INDEX IF NOT EXISTS idx_recall_entries_rule
+    ON recall_entries (rule_id, status);
+
+COMMENT ON TABLE recall_entries IS 'Recall lists: overdue patients per rule and the outcome of their recall';
+COMMENT ON COLUMN recall_entries.status IS 'OPEN (overdue), RESOLVED (no longer overdue), OPTED_OUT, CLOSED (by staff or patient inactive)';
+
+-- ====================
+-- OPT-OUTS
+-- ====================
+
+CREATE TABLE IF NOT EXISTS recall_opt_outs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    patient_id UUID NOT NULL REFERENCES patients(id) ON DELETE CASCADE,
+    -- NULL: every recall
+    rule_id UUID REFERENCES recall_rules(id) ON DELETE CASCADE,
+    reason VARCHAR(500),
+    created_by UUID NOT NULL REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_recall_opt_outs_rule
+    ON recall_opt_outs (patient_id, rule_id)
+    WHERE rule_id IS NOT NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_recall_opt_outs_all
+    ON recall_opt_outs (patient_id)
+    WHERE rule_id IS NULL;
+
+COMMENT ON TABLE recall_opt_outs IS 'Patients who asked not to receive recall reminders';
+
+GRANT SELECT, INSERT, UPDATE, DELETE ON recall_rules TO mpms_user;
+GRANT SELECT, INSERT, UPDATE, DELETE ON recall_runs TO mpms_user;
+GRANT SELECT, INSERT, UPDATE, DELETE ON recall_entries TO mpms_user;
+GRANT SELECT, INSERT, UPDATE, DELETE ON recall_opt_outs TO mpms_user;
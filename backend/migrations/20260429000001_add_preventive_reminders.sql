@@ -0,0 +1,101 @@
+-- Migration: Birthday greetings and preventive screening reminders
+-- Date: 2026-04-29
+--
+-- The notification scheduler (services/notification_scheduler.rs) can queue
+-- a BIRTHDAY_GREETING to patients on their birthday, and a SCREENING_REMINDER
+-- to patients due for one of the practice's screening programs. Both are off
+-- until the practice enables them. A program applies to patients of a sex
+-- and age range, every interval_months; an imaging order of its modality or
+-- a lab order/result for one of its LOINC codes within the interval counts
+-- as the screening done (models/preventive_reminder.rs).
+
+-- ====================
+-- NOTIFICATION TYPES
+-- ====================
+
+ALTER TABLE notification_queue
+    DROP CONSTRAINT IF EXISTS notification_queue_notification_type_check;
+ALTER TABLE notification_queue
+    ADD CONSTRAINT notification_queue_notification_type_check CHECK (
+        notification_type IN ('APPOINTMENT_REMINDER', 'APPOINTMENT_BOOKED', 'APPOINTMENT_CONFIRMATION',
+                              'APPOINTMENT_CANCELLATION', 'VISIT_SUMMARY', 'PRESCRIPTION_READY',
+                              'FOLLOW_UP_REMINDER', 'CUSTOM', 'MARKETING', 'REFERRAL_REMINDER',
+                              'REFILL_REMINDER', 'BIRTHDAY_GREETING', 'SCREENING_REMINDER')
+    );
+
+-- Birthday and screening deduplication look up earlier reminders by metadata
+CREATE INDEX IF NOT EXISTS idx_notification_queue_preventive
+    ON notification_queue (patient_id, notification_type)
+    WHERE notification_type IN ('BIRTHDAY_GREETING', 'SCREENING_REMINDER');
+
+-- ====================
+-- SETTINGS
+-- ====================
+
+INSERT INTO system_settings (
+    setting_key, setting_group, setting_name, setting_value,
+    value_type, description, default_value, is_public, is_encrypted, is_readonly
+) VALUES
+(
+    'birthday_greetings_enabled',
+    'notification',
+    'Birthday Greetings Enabled',
+    'false',
+    'BOOLEAN',
+    'Email a birthday greeting to active patients on their birthday.',
+    'false',
+    false,
+    false,
+    false
+),
+(
+    'birthday_greeting_message',
+    'notification',
+    'Birthday Greeting Message',
+    '"Everyone at the practice wishes you a very happy birthday and a healthy year ahead."',
+    'STRING',
+    'Message of the birthday greeting, after the salutation.',
+    '"Everyone at the practice wishes you a very happy birthday and a healthy year ahead."',
+    false,
+    false,
+    false
+),
+(
+    'screening_reminders_enabled',
+    'notification',
+    'Screening Reminders Enabled',
+    'false',
+    'BOOLEAN',
+    'Email patients due for one of the screening programs.',
+    'false',
+    false,
+    false,
+    false
+),
+(
+    'screening_programs',
+    'notification',
+    'Screening Programs',
+    '[
+        {"code": "MAMMOGRAPHY", "name": "Mammography", "sex": "F", "min_age": 50, "max_age": 69,
+         "interval_months": 24, "imaging_modality": "MAMMOGRAPHY"},
+        {"code": "COLONOSCOPY", "name": "Colonoscopy", "min_age": 50, "max_age": 74,
+         "interval_months": 120},
+        {"code": "FLU_SHOT", "name": "Flu shot", "min_age": 65,
+         "interval_months": 12, "season_months": [10, 11]}
+    ]',
+    'ARRAY',
+    'Screening programs patients are reminded of: code, name, optional sex (M/F), min_age and max_age, interval_months, optional season_months (1-12), imaging_modality and lab_loinc_codes that count as the screening done, enabled.',
+    '[
+        {"code": "MAMMOGRAPHY", "name": "Mammography", "sex": "F", "min_age": 50, "max_age": 69,
+         "interval_months": 24, "imaging_modality": "MAMMOGRAPHY"},
+        {"code": "COLONOSCOPY", "name": "Colonoscopy", "min_age": 50, "max_age": 74,
+         "interval_months": 120},
+        {"code": "FLU_SHOT", "name": "Flu shot", "min_age": 65,
+         "interval_months": 12, "season_months": [10, 11]}
+    ]',
+    false,
+    false,
+    false
+)
+ON CONFLICT (setting_key) DO NOTHING;
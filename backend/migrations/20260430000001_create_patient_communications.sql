@@ -0,0 +1,131 @@
+-- Migration: Patient communication log
+-- Date: 2026-04-30
+--
+-- One timeline of what the practice communicated to each patient. Entries
+-- are recorded automatically when a notification to the patient is sent
+-- (NOTIFICATION) or a generated document is marked delivered (DOCUMENT), and
+-- by staff for phone calls, letters and other contacts (MANUAL). Entries are
+-- not edited; staff add annotations instead. Manual notes and annotations
+-- are encrypted.
+
+-- ====================
+-- COMMUNICATIONS
+-- ====================
+
+CREATE TABLE IF NOT EXISTS patient_communications (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    patient_id UUID NOT NULL REFERENCES patients(id) ON DELETE CASCADE,
+    source VARCHAR(20) NOT NULL CHECK (source IN ('NOTIFICATION', 'DOCUMENT', 'MANUAL')),
+    channel VARCHAR(20) NOT NULL
+        CHECK (channel IN ('EMAIL', 'SMS', 'WHATSAPP', 'PHONE', 'LETTER', 'IN_PERSON', 'OTHER')),
+    direction VARCHAR(10) NOT NULL DEFAULT 'OUTBOUND' CHECK (direction IN ('OUTBOUND', 'INBOUND')),
+    notification_id UUID REFERENCES notification_queue(id) ON DELETE SET NULL,
+    document_id UUID REFERENCES generated_documents(id) ON DELETE SET NULL,
+    -- Email address, phone number or name the communication went to
+    recipient VARCHAR(255),
+    subject VARCHAR(255),
+    notes TEXT,                        -- 🔒 ENCRYPT
+    occurred_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_by UUID NOT NULL REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- A notification or document delivery is logged once
+CREATE UNIQUE INDEX IF NOT EXISTS idx_patient_communications_notification
+    ON patient_communications (notification_id)
+    WHERE notification_id IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_patient_communications_document
+    ON patient_communications (document_id)
+    WHERE document_id IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_patient_communications_patient
+    ON patient_communications (patient_id, occurred_at DESC);
+
+COMMENT ON TABLE patient_communications IS 'Per-patient log of notifications sent, documents delivered and manually recorded contacts';
+COMMENT ON COLUMN patient_communications.notes IS '🔒 ENCRYPTED - Notes of a manually recorded contact';
+
+-- No new entries for erased or merged patients
+CREATE TRIGGER trigger_prevent_anonymized_patient_communication
+    BEFORE INSERT ON patient_communications
+    FOR EACH ROW
+    EXECUTE FUNCTION prevent_anonymized_patient_reference();
+
+-- ====================
+-- ANNOTATIONS
+-- ====================
+
+CREATE TABLE IF NOT EXISTS patient_communication_annotations (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    communication_id UUID NOT NULL REFERENCES patient_communications(id) ON DELETE CASCADE,
+    note TEXT NOT NULL,                -- 🔒 ENCRYPT
+    created_by UUID NOT NULL REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_patient_communication_annotations_entry
+    ON patient_communication_annotations (communication_id, created_at);
+
+COMMENT ON TABLE patient_communication_annotations IS 'Staff notes added to communication log entries';
+COMMENT ON COLUMN patient_communication_annotations.note IS '🔒 ENCRYPTED - Annotation text';
+
+-- ====================
+-- RLS
+-- ====================
+
+ALTER TABLE patient_communications ENABLE ROW LEVEL SECURITY;
+ALTER TABLE patient_communications FORCE ROW LEVEL SECURITY;
+
+CREATE POLICY patient_communications_select_policy ON patient_communications
+    FOR SELECT
+    USING (is_doctor() OR is_nurse() OR is_receptionist());
+
+CREATE POLICY patient_communications_insert_policy ON patient_communications
+    FOR INSERT
+    WITH CHECK (is_doctor() OR is_nurse() OR is_receptionist());
+
+-- Entries are only rewritten by patient merges and erasures
+CREATE POLICY patient_communications_update_policy ON patient_communications
+    FOR UPDATE
+    USING (is_admin())
+    WITH CHECK (is_admin());
+
+ALTER TABLE patient_communication_annotations ENABLE ROW LEVEL SECURITY;
+ALTER TABLE patient_communication_annotations FORCE ROW LEVEL SECURITY;
+
+CREATE POLICY patient_communication_annotations_select_policy ON patient_communication_annotations
+    FOR SELECT
+    USING (is_doctor() OR is_nurse() OR is_receptionist());
+
+CREATE POLICY patient_communication_annotations_insert_policy ON patient_communication_annotations
+    FOR INSERT
+    WITH CHECK (is_doctor() OR is_nurse() OR is_receptionist());
+
+CREATE POLICY patient_communication_annotations_delete_policy ON patient_communication_annotations
+    FOR DELETE
+    USING (is_admin());
+
+GRANT SELECT, INSERT, UPDATE ON patient_communications TO mpms_user;
+GRANT SELECT, INSERT, DELETE ON patient_communication_annotations TO mpms_user;
+
+-- ====================
+-- CASBIN POLICIES
+-- ====================
+-- Same rules as casbin/policy.csv, for installs that already seeded the table
+
+INSERT INTO casbin_rules (ptype, v0, v1, v2)
+SELECT v.ptype, v.v0, v.v1, v.v2
+FROM (VALUES
+    ('p', 'ADMIN', 'communications', 'create'),
+    ('p', 'ADMIN', 'communications', 'read'),
+    ('p', 'ADMIN', 'communications', 'annotate'),
+    ('p', 'DOCTOR', 'communications', 'create'),
+    ('p', 'DOCTOR', 'communications', 'read'),
+    ('p', 'DOCTOR', 'communications', 'annotate'),
+    ('p', 'NURSE', 'communications', 'create'),
+    ('p', 'NURSE', 'communications', 'read'),
+    ('p', 'NURSE', 'communications', 'annotate'),
+    ('p', 'RECEPTIONIST', 'communications', 'create'),
+    ('p', 'RECEPTIONIST', 'communications', 'read'),
+    ('p', 'RECEPTIONIST', 'communications', 'annotate')
+) AS v (ptype, v0, v1, v2)
+WHERE EXISTS (SELECT 1 FROM casbin_rules)
+ON CONFLICT (ptype, v0, v1, v2, v3, v4, v5) DO NOTHING;
@@ -0,0 +1,170 @@
+-- Migration: Secure practice-patient messaging
+-- Date: 2026-05-01
+--
+-- Threaded conversations between the practice and a patient. Staff start a
+-- thread or reply from the provider inbox; patients will read and answer
+-- through the patient portal or a secure link, so a message is sent by the
+-- PRACTICE (with the staff member) or by the PATIENT. Subjects and bodies
+-- are encrypted. Attachments are documents of the patient record
+-- (patient_attachments), so anything shared in a conversation stays on the
+-- record. Read receipts: read_by_patient_at on practice messages,
+-- read_by_practice_at/read_by on patient messages.
+
+-- ====================
+-- THREADS
+-- ====================
+
+CREATE TABLE IF NOT EXISTS patient_message_threads (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    patient_id UUID NOT NULL REFERENCES patients(id) ON DELETE CASCADE,
+    subject TEXT NOT NULL,             -- 🔒 ENCRYPT
+    status VARCHAR(20) NOT NULL DEFAULT 'OPEN' CHECK (status IN ('OPEN', 'CLOSED')),
+    -- Staff member handling the conversation; the whole practice when NULL
+    assigned_to UUID REFERENCES users(id),
+    last_message_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    closed_at TIMESTAMPTZ,
+    closed_by UUID REFERENCES users(id),
+    -- NULL when the patient started the conversation
+    created_by UUID REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT patient_message_threads_closed CHECK ((status = 'CLOSED') = (closed_at IS NOT NULL))
+);
+
+CREATE INDEX IF NOT EXISTS idx_patient_message_threads_patient
+    ON patient_message_threads (patient_id, last_message_at DESC);
+CREATE INDEX IF NOT EXISTS idx_patient_message_threads_inbox
+    ON patient_message_threads (status, last_message_at DESC);
+CREATE INDEX IF NOT EXISTS idx_patient_message_threads_assigned
+    ON patient_message_threads (assigned_to, status)
+    WHERE assigned_to IS NOT NULL;
+
+COMMENT ON TABLE patient_message_threads IS 'Secure conversations between the practice and a patient';
+COMMENT ON COLUMN patient_message_threads.subject IS '🔒 ENCRYPTED - Thread subject';
+
+-- No new conversations with erased or merged patients
+CREATE TRIGGER trigger_prevent_anonymized_patient_message_thread
+    BEFORE INSERT ON patient_message_threads
+    FOR EACH ROW
+    EXECUTE FUNCTION prevent_anonymized_patient_reference();
+
+-- ====================
+-- MESSAGES
+-- ====================
+
+CREATE TABLE IF NOT EXISTS patient_messages (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    thread_id UUID NOT NULL REFERENCES patient_message_threads(id) ON DELETE CASCADE,
+    sender VARCHAR(20) NOT NULL CHECK (sender IN ('PRACTICE', 'PATIENT')),
+    sender_user_id UUID REFERENCES users(id),
+    body TEXT NOT NULL,                -- 🔒 ENCRYPT
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    -- Read receipt of a practice message
+    read_by_patient_at TIMESTAMPTZ,
+    -- Read receipt of a patient message
+    read_by_practice_at TIMESTAMPTZ,
+    read_by UUID REFERENCES users(id),
+
+    CONSTRAINT patient_messages_sender_user CHECK ((sender = 'PRACTICE') = (sender_user_id IS NOT NULL)),
+    CONSTRAINT patient_messages_practice_receipt CHECK (sender = 'PATIENT' OR read_by_practice_at IS NULL),
+    CONSTRAINT patient_messages_patient_receipt CHECK (sender = 'PRACTICE' OR read_by_patient_at IS NULL)
+);
+
+CREATE INDEX IF NOT EXISTS idx_patient_messages_thread
+    ON patient_messages (thread_id, created_at);
+-- Unread counts of the provider inbox
+CREATE INDEX IF NOT EXISTS idx_patient_messages_unread
+    ON patient_messages (thread_id)
+    WHERE sender = 'PATIENT' AND read_by_practice_at IS NULL;
+
+COMMENT ON TABLE patient_messages IS 'Messages of a practice-patient conversation';
+COMMENT ON COLUMN patient_messages.body IS '🔒 ENCRYPTED - Message text';
+
+CREATE TABLE IF NOT EXISTS patient_message_attachments (
+    message_id UUID NOT NULL REFERENCES patient_messages(id) ON DELETE CASCADE,
+    attachment_id UUID NOT NULL REFERENCES patient_attachments(id),
+    PRIMARY KEY (message_id, attachment_id)
+);
+
+COMMENT ON TABLE patient_message_attachments IS 'Patient record documents shared in a message';
+
+-- ====================
+-- RLS
+-- ====================
+-- Conversations may carry clinical content: only doctors (and admins) and
+-- nurses, the roles that read the patient record, see them.
+
+ALTER TABLE patient_message_threads ENABLE ROW LEVEL SECURITY;
+ALTER TABLE patient_message_threads FORCE ROW LEVEL SECURITY;
+
+CREATE POLICY patient_message_threads_select_policy ON patient_message_threads
+    FOR SELECT
+    USING (is_doctor() OR is_nurse());
+
+CREATE POLICY patient_message_threads_insert_policy ON patient_message_threads
+    FOR INSERT
+    WITH CHECK ((is_doctor() OR is_nurse()) AND created_by = get_current_user_id());
+
+CREATE POLICY patient_message_threads_update_policy ON patient_message_threads
+    FOR UPDATE
+    USING (is_doctor() OR is_nurse())
+    WITH CHECK (is_doctor() OR is_nurse());
+
+ALTER TABLE patient_messages ENABLE ROW LEVEL SECURITY;
+ALTER TABLE patient_messages FORCE ROW LEVEL SECURITY;
+
+CREATE POLICY patient_messages_select_policy ON patient_messages
+    FOR SELECT
+    USING (is_doctor() OR is_nurse());
+
+-- Staff only write messages in the practice's name, as themselves
+CREATE POLICY patient_messages_insert_policy ON patient_messages
+    FOR INSERT
+    WITH CHECK (
+        (is_doctor() OR is_nurse())
+        AND sender = 'PRACTICE'
+        AND sender_user_id = get_current_user_id()
+    );
+
+-- Read receipts, and scrubbing on erasure
+CREATE POLICY patient_messages_update_policy ON patient_messages
+    FOR UPDATE
+    USING (is_doctor() OR is_nurse())
+    WITH CHECK (is_doctor() OR is_nurse());
+
+ALTER TABLE patient_message_attachments ENABLE ROW LEVEL SECURITY;
+ALTER TABLE patient_message_attachments FORCE ROW LEVEL SECURITY;
+
+CREATE POLICY patient_message_attachments_select_policy ON patient_message_attachments
+    FOR SELECT
+    USING (is_doctor() OR is_nurse());
+
+CREATE POLICY patient_message_attachments_insert_policy ON patient_message_attachments
+    FOR INSERT
+    WITH CHECK (is_doctor() OR is_nurse());
+
+GRANT SELECT, INSERT, UPDATE ON patient_message_threads TO mpms_user;
+GRANT SELECT, INSERT, UPDATE ON patient_messages TO mpms_user;
+GRANT SELECT, INSERT ON patient_message_attachments TO mpms_user;
+
+-- ====================
+-- CASBIN POLICIES
+-- ====================
+-- Same rules as casbin/policy.csv, for installs that already seeded the table
+
+INSERT INTO casbin_rules (ptype, v0, v1, v2)
+SELECT v.ptype, v.v0, v.v1, v.v2
+FROM (VALUES
+    ('p', 'ADMIN', 'messages', 'create'),
+    ('p', 'ADMIN', 'messages', 'read'),
+    ('p', 'ADMIN', 'messages', 'update'),
+    ('p', 'DOCTOR', 'messages', 'create'),
+    ('p', 'DOCTOR', 'messages', 'read'),
+    ('p', 'DOCTOR', 'messages', 'update'),
+    ('p', 'NURSE', 'messages', 'create'),
+    ('p', 'NURSE', 'messages', 'read'),
+    ('p', 'NURSE', 'messages', 'update')
+) AS v (ptype, v0, v1, v2)
+WHERE EXISTS (SELECT 1 FROM casbin_rules)
+ON CONFLICT (ptype, v0, v1, v2, v3, v4, v5) DO NOTHING;
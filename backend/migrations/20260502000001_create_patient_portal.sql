@@ -0,0 +1,192 @@
+-- Migration: Patient portal
+-- Date: 2026-05-02
+--
+-- Patients sign in to the portal (/api/portal/v1) with a one-time link
+-- emailed to the address on their record, after giving that address and
+-- their date of birth. The link yields a short portal session; the portal
+-- token is a separate token type that staff routes never accept, and the
+-- portal never reads the staff RBAC policies.
+--
+-- Database sessions of the portal run as role 'PATIENT' with
+-- app.portal_patient_id set. The staff policies do not match that role, so
+-- the portal policies below are the only access a patient has: their own
+-- record (contact details are the only fields they may change), their
+-- appointments and their signed documents.
+
+-- ====================
+-- LOGIN LINKS
+-- ====================
+-- Only the SHA-256 hash of the link token is stored.
+
+CREATE TABLE IF NOT EXISTS patient_portal_login_tokens (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    patient_id UUID NOT NULL REFERENCES patients(id) ON DELETE CASCADE,
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    expires_at TIMESTAMPTZ NOT NULL,
+    used_at TIMESTAMPTZ,
+    ip_address INET,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_patient_portal_login_tokens_patient
+    ON patient_portal_login_tokens (patient_id, created_at DESC);
+
+COMMENT ON TABLE patient_portal_login_tokens IS 'One-time patient portal sign-in links';
+COMMENT ON COLUMN patient_portal_login_tokens.token_hash IS 'SHA-256 hex digest of the link token (token itself is never stored)';
+
+-- ====================
+-- SESSIONS
+-- ====================
+-- The portal token carries the session ID; the row is checked on every
+-- request so signing out (or the practice revoking access) takes effect
+-- immediately.
+
+CREATE TABLE IF NOT EXISTS patient_portal_sessions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    patient_id UUID NOT NULL REFERENCES patients(id) ON DELETE CASCADE,
+    login_token_id UUID REFERENCES patient_portal_login_tokens(id) ON DELETE SET NULL,
+    ip_address INET,
+    user_agent TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_used_at TIMESTAMPTZ,
+    expires_at TIMESTAMPTZ NOT NULL,
+    revoked_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_patient_portal_sessions_patient_active
+    ON patient_portal_sessions (patient_id, expires_at)
+    WHERE revoked_at IS NULL;
+
+COMMENT ON TABLE patient_portal_sessions IS 'Patient portal sessions (one per used sign-in link)';
+
+GRANT SELECT, INSERT, UPDATE ON patient_portal_login_tokens TO mpms_user;
+GRANT SELECT, INSERT, UPDATE ON patient_portal_sessions TO mpms_user;
+
+-- ====================
+-- SIGN-IN LOOKUP
+-- ====================
+-- Sign-in happens before there is any RLS context, so the patients matching
+-- an email blind index are found through this function. It only returns
+-- records that may use the portal, and only the fields needed to check the
+-- date of birth and send the link.
+
+CREATE OR REPLACE FUNCTION portal_login_candidates(p_email_bidx TEXT)
+RETURNS TABLE (patient_id UUID, first_name TEXT, date_of_birth TEXT, email TEXT)
+SECURITY DEFINER
+SET search_path = public
+AS $$
+    SELECT p.id, p.first_name::TEXT, p.date_of_birth::TEXT, p.email::TEXT
+    FROM patients p
+    WHERE p.email_bidx = p_email_bidx
+      AND p.status = 'ACTIVE'
+      AND p.anonymized_at IS NULL
+      AND p.merged_into_id IS NULL
+      AND p.deleted_at IS NULL
+$$ LANGUAGE sql STABLE;
+
+REVOKE ALL ON FUNCTION portal_login_candidates(TEXT) FROM PUBLIC;
+GRANT EXECUTE ON FUNCTION portal_login_candidates(TEXT) TO mpms_user;
+
+-- ====================
+-- RLS
+-- ====================
+
+-- Patient of the current portal session (NULL for staff sessions)
+CREATE OR REPLACE FUNCTION portal_patient_id()
+RETURNS UUID AS $$
+BEGIN
+    IF current_setting('app.current_user_role', TRUE) IS DISTINCT FROM 'PATIENT' THEN
+        RETURN NULL;
+    END IF;
+    RETURN NULLIF(current_setting('app.portal_patient_id', TRUE), '')::UUID;
+EXCEPTION
+    WHEN OTHERS THEN
+        RETURN NULL;
+END;
+$$ LANGUAGE plpgsql STABLE;
+
+COMMENT ON FUNCTION portal_patient_id() IS 'Patient of the current patient portal session, from app.portal_patient_id';
+
+DROP POLICY IF EXISTS patients_portal_select_policy ON patients;
+CREATE POLICY patients_portal_select_policy ON patients
+    FOR SELECT
+    USING (id = portal_patient_id());
+
+DROP POLICY IF EXISTS patients_portal_update_policy ON patients;
+CREATE POLICY patients_portal_update_policy ON patients
+    FOR UPDATE
+    USING (id = portal_patient_id())
+    WITH CHECK (id = portal_patient_id());
+
+-- Patients only change their contact details (and the blind indexes derived
+-- from them); everything else on the record is the practice's
+CREATE OR REPLACE FUNCTION prevent_portal_patient_field_change()
+RETURNS TRIGGER AS $$
+DECLARE
+    contact_columns TEXT[] := ARRAY[
+        'phone_primary', 'phone_secondary', 'email', 'address', 'preferred_contact_method',
+        'phone_bidx', 'email_bidx', 'search_trigram_bidx', 'updated_at'
+    ];
+BEGIN
+    IF current_setting('app.current_user_role', TRUE) = 'PATIENT'
+       AND (to_jsonb(NEW) - contact_columns) IS DISTINCT FROM (to_jsonb(OLD) - contact_columns) THEN
+        RAISE EXCEPTION 'Patients may only change their contact details';
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trigger_prevent_portal_patient_field_change ON patients;
+CREATE TRIGGER trigger_prevent_portal_patient_field_change
+    BEFORE UPDATE ON patients
+    FOR EACH ROW
+    EXECUTE FUNCTION prevent_portal_patient_field_change();
+
+DROP POLICY IF EXISTS appointments_portal_select_policy ON appointments;
+CREATE POLICY appointments_portal_select_policy ON appointments
+    FOR SELECT
+    USING (patient_id = portal_patient_id());
+
+-- Signed documents only: drafts stay with the practice
+DROP POLICY IF EXISTS generated_documents_portal_select_policy ON generated_documents;
+CREATE POLICY generated_documents_portal_select_policy ON generated_documents
+    FOR SELECT
+    USING (
+        patient_id = portal_patient_id()
+        AND is_signed = true
+        AND status IN ('GENERATED', 'DELIVERED')
+    );
+
+-- Queued notifications follow a contact change (see contact_propagation)
+DROP POLICY IF EXISTS notification_queue_portal_select_policy ON notification_queue;
+CREATE POLICY notification_queue_portal_select_policy ON notification_queue
+    FOR SELECT
+    USING (patient_id = portal_patient_id());
+
+DROP POLICY IF EXISTS notification_queue_portal_update_policy ON notification_queue;
+CREATE POLICY notification_queue_portal_update_policy ON notification_queue
+    FOR UPDATE
+    USING (patient_id = portal_patient_id())
+    WITH CHECK (patient_id = portal_patient_id());
+
+-- ====================
+-- SETTINGS
+-- ====================
+
+INSERT INTO system_settings (
+    setting_key, setting_group, setting_name, setting_value,
+    value_type, description, default_value, is_public, is_encrypted, is_readonly
+) VALUES
+(
+    'portal.enabled',
+    'portal',
+    'Patient Portal Enabled',
+    'false',
+    'BOOLEAN',
+    'Let patients sign in to the patient portal with a link emailed to them, to see their upcoming appointments, download their signed documents and update their contact details.',
+    'false',
+    false,
+    false,
+    false
+)
+ON CONFLICT (setting_key) DO NOTHING;
@@ -0,0 +1,268 @@
+-- Migration: Public self-booking
+-- Date: 2026-05-03
+--
+-- Patients book appointments themselves through the public booking API
+-- (/api/booking/v1). They pick one of the offered slots, fill in their
+-- details and solve a CAPTCHA; the request is kept until they open the
+-- verification link emailed to them. Verified requests become PENDING
+-- appointments, which hold the slot until staff accept (SCHEDULED or
+-- CONFIRMED) or decline (CANCELLED) them.
+--
+-- Booking runs without a user: database sessions act as role 'BOOKING',
+-- which may only add patients and PENDING appointments (see the policies
+-- below) and reads busy times through a function that returns no patient
+-- data.
+
+-- ====================
+-- PENDING STATUS
+-- ====================
+
+ALTER TABLE appointments DROP CONSTRAINT IF EXISTS appointments_status_check;
+ALTER TABLE appointments ADD CONSTRAINT appointments_status_check CHECK (
+    status IN ('PENDING', 'SCHEDULED', 'CONFIRMED', 'IN_PROGRESS', 'COMPLETED', 'CANCELLED', 'NO_SHOW')
+);
+
+COMMENT ON COLUMN appointments.status IS 'Appointment status: PENDING (booked online) → SCHEDULED → CONFIRMED → IN_PROGRESS → COMPLETED (or CANCELLED/NO_SHOW)';
+
+CREATE OR REPLACE FUNCTION validate_appointment_status_transition()
+RETURNS TRIGGER AS $$
+BEGIN
+    -- PENDING can go to: SCHEDULED, CONFIRMED (accepted), CANCELLED (declined)
+    IF OLD.status = 'PENDING' AND NEW.status NOT IN ('PENDING', 'SCHEDULED', 'CONFIRMED', 'CANCELLED') THEN
+        RAISE EXCEPTION 'Invalid status transition from PENDING to %', NEW.status;
+    END IF;
+
+    -- SCHEDULED can go to: CONFIRMED, CANCELLED, NO_SHOW
+    IF OLD.status = 'SCHEDULED' AND NEW.status NOT IN ('SCHEDULED', 'CONFIRMED', 'CANCELLED', 'NO_SHOW') THEN
+        RAISE EXCEPTION 'Invalid status transition from SCHEDULED to %', NEW.status;
+    END IF;
+
+    -- CONFIRMED can go to: IN_PROGRESS, CANCELLED, NO_SHOW
+    IF OLD.status = 'CONFIRMED' AND NEW.status NOT IN ('CONFIRMED', 'IN_PROGRESS', 'CANCELLED', 'NO_SHOW') THEN
+        RAISE EXCEPTION 'Invalid status transition from CONFIRMED to %', NEW.status;
+    END IF;
+
+    -- IN_PROGRESS can go to: COMPLETED, CANCELLED
+    IF OLD.status = 'IN_PROGRESS' AND NEW.status NOT IN ('IN_PROGRESS', 'COMPLETED', 'CANCELLED') THEN
+        RAISE EXCEPTION 'Invalid status transition from IN_PROGRESS to %', NEW.status;
+    END IF;
+
+    -- COMPLETED cannot be changed
+    IF OLD.status = 'COMPLETED' AND NEW.status != 'COMPLETED' THEN
+        RAISE EXCEPTION 'Cannot change status from COMPLETED';
+    END IF;
+
+    -- CANCELLED and NO_SHOW are final states
+    IF OLD.status IN ('CANCELLED', 'NO_SHOW') AND NEW.status != OLD.status THEN
+        RAISE EXCEPTION 'Cannot change status from % to %', OLD.status, NEW.status;
+    END IF;
+
+    -- Automatically set confirmed_at when moving to CONFIRMED
+    IF NEW.status = 'CONFIRMED' AND OLD.status != 'CONFIRMED' AND NEW.confirmed_at IS NULL THEN
+        NEW.confirmed_at := NOW();
+    END IF;
+
+    -- Automatically set cancelled_at when moving to CANCELLED
+    IF NEW.status = 'CANCELLED' AND OLD.status != 'CANCELLED' AND NEW.cancelled_at IS NULL THEN
+        NEW.cancelled_at := NOW();
+    END IF;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Confirmation codes are numbered from every appointment of the year, not
+-- only those visible to the session (same fix as the MRN generation)
+CREATE OR REPLACE FUNCTION generate_confirmation_code()
+RETURNS TRIGGER AS $$
+DECLARE
+    year_part VARCHAR(4);
+    seq_part VARCHAR(6);
+BEGIN
+    IF NEW.confirmation_code IS NULL OR NEW.confirmation_code = '' THEN
+        year_part := TO_CHAR(CURRENT_DATE, 'YYYY');
+
+        SELECT LPAD(
+            (COUNT(*) + 1)::TEXT,
+            4,
+            '0'
+        ) INTO seq_part
+        FROM appointments
+        WHERE confirmation_code LIKE 'APT-' || year_part || '-%';
+
+        NEW.confirmation_code := 'APT-' || year_part || '-' || seq_part;
+    END IF;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql SECURITY DEFINER SET search_path = public;
+
+-- ====================
+-- BOOKING REQUESTS
+-- ====================
+-- A request waits here until its verification link is opened. Patient
+-- details are encrypted like the patient record; only the SHA-256 hash of
+-- the verification token is stored.
+
+CREATE TABLE IF NOT EXISTS booking_requests (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    provider_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    scheduled_start TIMESTAMPTZ NOT NULL,
+    duration_minutes INT NOT NULL CHECK (duration_minutes > 0 AND duration_minutes <= 480),
+    appointment_type VARCHAR(50) NOT NULL,
+
+    first_name TEXT NOT NULL,        -- 🔒 Encrypted
+    last_name TEXT NOT NULL,         -- 🔒 Encrypted
+    date_of_birth TEXT NOT NULL,     -- 🔒 Encrypted
+    email TEXT NOT NULL,             -- 🔒 Encrypted
+    phone TEXT,                      -- 🔒 Encrypted
+    reason TEXT,                     -- 🔒 Encrypted
+    email_bidx VARCHAR(64) NOT NULL,
+
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    expires_at TIMESTAMPTZ NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'UNVERIFIED' CHECK (
+        status IN ('UNVERIFIED', 'BOOKED', 'UNAVAILABLE')
+    ),
+    verified_at TIMESTAMPTZ,
+    patient_id UUID REFERENCES patients(id) ON DELETE SET NULL,
+    appointment_id UUID REFERENCES appointments(id) ON DELETE SET NULL,
+
+    ip_address INET,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_booking_requests_email
+    ON booking_requests (email_bidx, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_booking_requests_unverified
+    ON booking_requests (expires_at)
+    WHERE status = 'UNVERIFIED';
+
+COMMENT ON TABLE booking_requests IS 'Online booking requests waiting for (or done with) email verification';
+COMMENT ON COLUMN booking_requests.token_hash IS 'SHA-256 hex digest of the verification token (token itself is never stored)';
+COMMENT ON COLUMN booking_requests.status IS 'UNVERIFIED, BOOKED (PENDING appointment created) or UNAVAILABLE (slot taken before verification)';
+
+GRANT SELECT, INSERT, UPDATE ON booking_requests TO mpms_user;
+
+-- ====================
+-- AVAILABILITY LOOKUP
+-- ====================
+-- Slots are offered before there is any RLS context, so the busy times of
+-- a provider come from this function: start and end only, nothing about the
+-- patient or the visit.
+
+CREATE OR REPLACE FUNCTION booking_busy_intervals(
+    p_provider_id UUID,
+    p_from TIMESTAMPTZ,
+    p_to TIMESTAMPTZ
+)
+RETURNS TABLE (scheduled_start TIMESTAMPTZ, scheduled_end TIMESTAMPTZ)
+SECURITY DEFINER
+SET search_path = public
+AS $$
+    SELECT a.scheduled_start, a.scheduled_end
+    FROM appointments a
+    WHERE a.provider_id = p_provider_id
+      AND a.status NOT IN ('CANCELLED', 'NO_SHOW')
+      AND tstzrange(a.scheduled_start, a.scheduled_end) && tstzrange(p_from, p_to)
+    ORDER BY a.scheduled_start
+$$ LANGUAGE sql STABLE;
+
+REVOKE ALL ON FUNCTION booking_busy_intervals(UUID, TIMESTAMPTZ, TIMESTAMPTZ) FROM PUBLIC;
+GRANT EXECUTE ON FUNCTION booking_busy_intervals(UUID, TIMESTAMPTZ, TIMESTAMPTZ) TO mpms_user;
+
+-- ====================
+-- RLS
+-- ====================
+
+-- Whether the session is a public booking session
+CREATE OR REPLACE FUNCTION is_booking_session()
+RETURNS BOOLEAN AS $$
+BEGIN
+    RETURN current_setting('app.current_user_role', TRUE) = 'BOOKING';
+END;
+$$ LANGUAGE plpgsql STABLE;
+
+COMMENT ON FUNCTION is_booking_session() IS 'Whether the session books an appointment on behalf of a member of the public';
+
+-- New patients from online bookings (no staff author)
+DROP POLICY IF EXISTS patients_booking_insert_policy ON patients;
+CREATE POLICY patients_booking_insert_policy ON patients
+    FOR INSERT
+    WITH CHECK (is_booking_session() AND created_by IS NULL);
+
+-- Online bookings only ever create PENDING appointments
+DROP POLICY IF EXISTS appointments_booking_insert_policy ON appointments;
+CREATE POLICY appointments_booking_insert_policy ON appointments
+    FOR INSERT
+    WITH CHECK (is_booking_session() AND status = 'PENDING' AND created_by IS NULL);
+
+-- ====================
+-- SETTINGS
+-- ====================
+
+INSERT INTO system_settings (
+    setting_key, setting_group, setting_name, setting_value,
+    value_type, description, default_value, is_public, is_encrypted, is_readonly
+) VALUES
+(
+    'booking.enabled',
+    'booking',
+    'Online Booking Enabled',
+    'false',
+    'BOOLEAN',
+    'Let patients request appointments online. Requests are verified by email and land as PENDING appointments for staff to accept.',
+    'false',
+    false,
+    false,
+    false
+),
+(
+    'booking.provider_ids',
+    'booking',
+    'Bookable Providers',
+    '[]',
+    'ARRAY',
+    'Doctors whose calendars are offered for online booking (user IDs). Empty: every active doctor.',
+    '[]',
+    false,
+    false,
+    false
+),
+(
+    'booking.appointment_types',
+    'booking',
+    'Bookable Appointment Types',
+    '["NEW_PATIENT", "FOLLOW_UP", "CONSULTATION", "ROUTINE_CHECKUP"]',
+    'ARRAY',
+    'Appointment types patients may book online.',
+    '["NEW_PATIENT", "FOLLOW_UP", "CONSULTATION", "ROUTINE_CHECKUP"]',
+    false,
+    false,
+    false
+),
+(
+    'booking.min_notice_hours',
+    'booking',
+    'Booking Minimum Notice (hours)',
+    '24',
+    'INTEGER',
+    'Slots starting sooner than this are not offered online.',
+    '24',
+    false,
+    false,
+    false
+),
+(
+    'booking.max_days_ahead',
+    'booking',
+    'Booking Horizon (days)',
+    '60',
+    'INTEGER',
+    'How many days ahead slots are offered online.',
+    '60',
+    false,
+    false,
+    false
+)
+ON CONFLICT (setting_key) DO NOTHING;
@@ -0,0 +1,71 @@
+-- Migration: Appointment confirm/cancel links
+-- Date: 2026-05-04
+--
+-- Appointment reminders carry signed links that let the patient confirm or
+-- cancel the appointment without signing in. A link acts as the patient
+-- (the portal role 'PATIENT' with app.portal_patient_id set) on that one
+-- appointment. The policies and trigger below let that role confirm or
+-- cancel its own appointments and change nothing else. When the patient
+-- cancels, the provider is emailed (PATIENT_CANCELLATION).
+
+-- ====================
+-- NOTIFICATION TYPE
+-- ====================
+
+ALTER TABLE notification_queue
+    DROP CONSTRAINT IF EXISTS notification_queue_notification_type_check;
+ALTER TABLE notification_queue
+    ADD CONSTRAINT notification_queue_notification_type_check CHECK (
+        notification_type IN ('APPOINTMENT_REMINDER', 'APPOINTMENT_BOOKED', 'APPOINTMENT_CONFIRMATION',
+                              'APPOINTMENT_CANCELLATION', 'VISIT_SUMMARY', 'PRESCRIPTION_READY',
+                              'FOLLOW_UP_REMINDER', 'CUSTOM', 'MARKETING', 'REFERRAL_REMINDER',
+                              'REFILL_REMINDER', 'BIRTHDAY_GREETING', 'SCREENING_REMINDER',
+                              'PATIENT_CANCELLATION')
+    );
+
+-- ====================
+-- RLS
+-- ====================
+
+DROP POLICY IF EXISTS appointments_portal_update_policy ON appointments;
+CREATE POLICY appointments_portal_update_policy ON appointments
+    FOR UPDATE
+    USING (patient_id = portal_patient_id())
+    WITH CHECK (patient_id = portal_patient_id());
+
+-- Patients only confirm or cancel; the rest of the appointment is the
+-- practice's
+CREATE OR REPLACE FUNCTION prevent_portal_appointment_change()
+RETURNS TRIGGER AS $$
+DECLARE
+    response_columns TEXT[] := ARRAY[
+        'status', 'confirmed_at', 'cancelled_at', 'cancellation_reason', 'updated_at'
+    ];
+BEGIN
+    IF current_setting('app.current_user_role', TRUE) = 'PATIENT' THEN
+        IF (to_jsonb(NEW) - response_columns) IS DISTINCT FROM (to_jsonb(OLD) - response_columns) THEN
+            RAISE EXCEPTION 'Patients may only confirm or cancel their appointments';
+        END IF;
+        IF NEW.status IS DISTINCT FROM OLD.status AND NEW.status NOT IN ('CONFIRMED', 'CANCELLED') THEN
+            RAISE EXCEPTION 'Patients may only confirm or cancel their appointments';
+        END IF;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trigger_prevent_portal_appointment_change ON appointments;
+CREATE TRIGGER trigger_prevent_portal_appointment_change
+    BEFORE UPDATE ON appointments
+    FOR EACH ROW
+    EXECUTE FUNCTION prevent_portal_appointment_change();
+
+-- The cancellation email to the provider is queued as the patient
+DROP POLICY IF EXISTS notification_queue_portal_insert_policy ON notification_queue;
+CREATE POLICY notification_queue_portal_insert_policy ON notification_queue
+    FOR INSERT
+    WITH CHECK (
+        patient_id = portal_patient_id()
+        AND notification_type = 'PATIENT_CANCELLATION'
+        AND created_by IS NULL
+    );
@@ -0,0 +1,138 @@
+-- Migration: Post-visit surveys
+-- Date: 2026-05-05
+--
+-- When the practice enables it, the notification scheduler emails patients a
+-- short survey (VISIT_SURVEY) after a COMPLETED appointment: a 0-10 score of
+-- how likely they are to recommend the practice (Net Promoter Score) and an
+-- optional comment. The survey link acts as the patient (the portal role
+-- 'PATIENT' with app.portal_patient_id set); the policies and trigger below
+-- let that role answer its own surveys once and change nothing else. Scores
+-- are summarized on the dashboard.
+
+-- ====================
+-- SURVEYS
+-- ====================
+
+CREATE TABLE IF NOT EXISTS visit_surveys (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    appointment_id UUID NOT NULL UNIQUE REFERENCES appointments(id) ON DELETE CASCADE,
+    patient_id UUID NOT NULL REFERENCES patients(id) ON DELETE CASCADE,
+    provider_id UUID NOT NULL REFERENCES users(id),
+    notification_id UUID REFERENCES notification_queue(id) ON DELETE SET NULL,
+    sent_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL,
+    score SMALLINT CHECK (score BETWEEN 0 AND 10),
+    comment TEXT,                      -- 🔒 ENCRYPT
+    responded_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT visit_surveys_answered CHECK ((score IS NULL) = (responded_at IS NULL))
+);
+
+CREATE INDEX IF NOT EXISTS idx_visit_surveys_sent
+    ON visit_surveys (sent_at);
+CREATE INDEX IF NOT EXISTS idx_visit_surveys_patient
+    ON visit_surveys (patient_id, sent_at DESC);
+
+COMMENT ON TABLE visit_surveys IS 'Satisfaction surveys sent to patients after completed appointments';
+COMMENT ON COLUMN visit_surveys.score IS 'How likely the patient is to recommend the practice (0-10)';
+COMMENT ON COLUMN visit_surveys.comment IS '🔒 ENCRYPTED - Free-text comment of the patient';
+
+-- No new surveys for erased or merged patients
+CREATE TRIGGER trigger_prevent_anonymized_visit_survey
+    BEFORE INSERT ON visit_surveys
+    FOR EACH ROW
+    EXECUTE FUNCTION prevent_anonymized_patient_reference();
+
+-- Patients only answer, once and before the link expires
+CREATE OR REPLACE FUNCTION prevent_portal_visit_survey_change()
+RETURNS TRIGGER AS $$
+DECLARE
+    answer_columns TEXT[] := ARRAY['score', 'comment', 'responded_at'];
+BEGIN
+    IF current_setting('app.current_user_role', TRUE) = 'PATIENT' THEN
+        IF (to_jsonb(NEW) - answer_columns) IS DISTINCT FROM (to_jsonb(OLD) - answer_columns) THEN
+            RAISE EXCEPTION 'Patients may only answer their surveys';
+        END IF;
+        IF OLD.responded_at IS NOT NULL OR OLD.expires_at <= NOW() THEN
+            RAISE EXCEPTION 'This survey can no longer be answered';
+        END IF;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trigger_prevent_portal_visit_survey_change
+    BEFORE UPDATE ON visit_surveys
+    FOR EACH ROW
+    EXECUTE FUNCTION prevent_portal_visit_survey_change();
+
+-- ====================
+-- NOTIFICATION TYPE
+-- ====================
+
+ALTER TABLE notification_queue
+    DROP CONSTRAINT IF EXISTS notification_queue_notification_type_check;
+ALTER TABLE notification_queue
+    ADD CONSTRAINT notification_queue_notification_type_check CHECK (
+        notification_type IN ('APPOINTMENT_REMINDER', 'APPOINTMENT_BOOKED', 'APPOINTMENT_CONFIRMATION',
+                              'APPOINTMENT_CANCELLATION', 'VISIT_SUMMARY', 'PRESCRIPTION_READY',
+                              'FOLLOW_UP_REMINDER', 'CUSTOM', 'MARKETING', 'REFERRAL_REMINDER',
+                              'REFILL_REMINDER', 'BIRTHDAY_GREETING', 'SCREENING_REMINDER',
+                              'PATIENT_CANCELLATION', 'VISIT_SURVEY')
+    );
+
+-- ====================
+-- RLS
+-- ====================
+
+ALTER TABLE visit_surveys ENABLE ROW LEVEL SECURITY;
+ALTER TABLE visit_surveys FORCE ROW LEVEL SECURITY;
+
+-- Results are read by those who read reports
+CREATE POLICY visit_surveys_select_policy ON visit_surveys
+    FOR SELECT
+    USING (is_doctor());
+
+-- Surveys are created by the scheduler
+CREATE POLICY visit_surveys_insert_policy ON visit_surveys
+    FOR INSERT
+    WITH CHECK (is_admin());
+
+-- Rewritten only by patient merges and erasures
+CREATE POLICY visit_surveys_update_policy ON visit_surveys
+    FOR UPDATE
+    USING (is_admin())
+    WITH CHECK (is_admin());
+
+CREATE POLICY visit_surveys_portal_select_policy ON visit_surveys
+    FOR SELECT
+    USING (patient_id = portal_patient_id());
+
+CREATE POLICY visit_surveys_portal_update_policy ON visit_surveys
+    FOR UPDATE
+    USING (patient_id = portal_patient_id())
+    WITH CHECK (patient_id = portal_patient_id());
+
+GRANT SELECT, INSERT, UPDATE ON visit_surveys TO mpms_user;
+
+-- ====================
+-- SETTINGS
+-- ====================
+
+INSERT INTO system_settings (
+    setting_key, setting_group, setting_name, setting_value,
+    value_type, description, default_value, is_public, is_encrypted, is_readonly
+) VALUES (
+    'visit_surveys_enabled',
+    'notification',
+    'Visit Surveys Enabled',
+    'false',
+    'BOOLEAN',
+    'Email patients a satisfaction survey after a completed appointment. Needs the public URL of the practice.',
+    'false',
+    false,
+    false,
+    false
+)
+ON CONFLICT (setting_key) DO NOTHING;
@@ -0,0 +1,142 @@
+-- Migration: Televisits
+-- Date: 2026-05-06
+--
+-- TELEVISIT appointments take place in a video room. Creating one provisions
+-- a room with the configured video provider (VIDEO_PROVIDER) and emails the
+-- patient a join link (TELEVISIT_INVITATION). The session keeps the room and
+-- when the doctor and the patient joined and left. The patient's link acts as
+-- the patient (the portal role 'PATIENT' with app.portal_patient_id set); the
+-- policies and trigger below let that role record its own attendance and
+-- change nothing else.
+
+-- ====================
+-- TELEVISIT TYPE
+-- ====================
+
+ALTER TABLE appointments DROP CONSTRAINT IF EXISTS appointments_type_check;
+ALTER TABLE appointments ADD CONSTRAINT appointments_type_check CHECK (
+    type IN ('NEW_PATIENT', 'FOLLOW_UP', 'URGENT', 'CONSULTATION', 'ROUTINE_CHECKUP', 'ACUPUNCTURE',
+             'TELEVISIT')
+);
+
+-- ====================
+-- SESSIONS
+-- ====================
+
+CREATE TABLE IF NOT EXISTS televisit_sessions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    appointment_id UUID NOT NULL UNIQUE REFERENCES appointments(id) ON DELETE CASCADE,
+    patient_id UUID NOT NULL REFERENCES patients(id) ON DELETE CASCADE,
+    video_provider VARCHAR(20) NOT NULL,
+    room_name VARCHAR(255) NOT NULL,
+    join_url TEXT NOT NULL,
+    invitation_notification_id UUID REFERENCES notification_queue(id) ON DELETE SET NULL,
+    provider_joined_at TIMESTAMPTZ,
+    provider_left_at TIMESTAMPTZ,
+    patient_joined_at TIMESTAMPTZ,
+    patient_left_at TIMESTAMPTZ,
+    created_by UUID REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_televisit_sessions_patient
+    ON televisit_sessions (patient_id);
+
+COMMENT ON TABLE televisit_sessions IS 'Video rooms of TELEVISIT appointments and attendance of doctor and patient';
+COMMENT ON COLUMN televisit_sessions.join_url IS 'Link that opens the video room';
+COMMENT ON COLUMN televisit_sessions.provider_joined_at IS 'First time the doctor joined';
+COMMENT ON COLUMN televisit_sessions.provider_left_at IS 'Last time the doctor left';
+COMMENT ON COLUMN televisit_sessions.patient_joined_at IS 'First time the patient joined';
+COMMENT ON COLUMN televisit_sessions.patient_left_at IS 'Last time the patient left';
+
+CREATE TRIGGER update_televisit_sessions_updated_at
+    BEFORE UPDATE ON televisit_sessions
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
+
+-- No new rooms for erased or merged patients
+CREATE TRIGGER trigger_prevent_anonymized_televisit_session
+    BEFORE INSERT ON televisit_sessions
+    FOR EACH ROW
+    EXECUTE FUNCTION prevent_anonymized_patient_reference();
+
+-- Patients only record their own attendance
+CREATE OR REPLACE FUNCTION prevent_portal_televisit_session_change()
+RETURNS TRIGGER AS $$
+DECLARE
+    attendance_columns TEXT[] := ARRAY['patient_joined_at', 'patient_left_at', 'updated_at'];
+BEGIN
+    IF current_setting('app.current_user_role', TRUE) = 'PATIENT' THEN
+        IF (to_jsonb(NEW) - attendance_columns) IS DISTINCT FROM (to_jsonb(OLD) - attendance_columns) THEN
+            RAISE EXCEPTION 'Patients may only record joining and leaving their televisits';
+        END IF;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trigger_prevent_portal_televisit_session_change
+    BEFORE UPDATE ON televisit_sessions
+    FOR EACH ROW
+    EXECUTE FUNCTION prevent_portal_televisit_session_change();
+
+-- ====================
+-- NOTIFICATION TYPE
+-- ====================
+
+ALTER TABLE notification_queue
+    DROP CONSTRAINT IF EXISTS notification_queue_notification_type_check;
+ALTER TABLE notification_queue
+    ADD CONSTRAINT notification_queue_notification_type_check CHECK (
+        notification_type IN ('APPOINTMENT_REMINDER', 'APPOINTMENT_BOOKED', 'APPOINTMENT_CONFIRMATION',
+                              'APPOINTMENT_CANCELLATION', 'VISIT_SUMMARY', 'PRESCRIPTION_READY',
+                              'FOLLOW_UP_REMINDER', 'CUSTOM', 'MARKETING', 'REFERRAL_REMINDER',
+                              'REFILL_REMINDER', 'BIRTHDAY_GREETING', 'SCREENING_REMINDER',
+                              'PATIENT_CANCELLATION', 'VISIT_SURVEY', 'TELEVISIT_INVITATION')
+    );
+
+-- ====================
+-- RLS
+-- ====================
+
+ALTER TABLE televisit_sessions ENABLE ROW LEVEL SECURITY;
+ALTER TABLE televisit_sessions FORCE ROW LEVEL SECURITY;
+
+-- Staff reach a session through its appointment, so the appointment
+-- policies (own patients, delegations) apply here too
+CREATE POLICY televisit_sessions_select_policy ON televisit_sessions
+    FOR SELECT
+    USING (
+        is_doctor()
+        AND EXISTS (SELECT 1 FROM appointments a WHERE a.id = televisit_sessions.appointment_id)
+    );
+
+CREATE POLICY televisit_sessions_insert_policy ON televisit_sessions
+    FOR INSERT
+    WITH CHECK (
+        is_doctor()
+        AND EXISTS (SELECT 1 FROM appointments a WHERE a.id = televisit_sessions.appointment_id)
+    );
+
+CREATE POLICY televisit_sessions_update_policy ON televisit_sessions
+    FOR UPDATE
+    USING (
+        is_doctor()
+        AND EXISTS (SELECT 1 FROM appointments a WHERE a.id = televisit_sessions.appointment_id)
+    )
+    WITH CHECK (
+        is_doctor()
+        AND EXISTS (SELECT 1 FROM appointments a WHERE a.id = televisit_sessions.appointment_id)
+    );
+
+CREATE POLICY televisit_sessions_portal_select_policy ON televisit_sessions
+    FOR SELECT
+    USING (patient_id = portal_patient_id());
+
+CREATE POLICY televisit_sessions_portal_update_policy ON televisit_sessions
+    FOR UPDATE
+    USING (patient_id = portal_patient_id())
+    WITH CHECK (patient_id = portal_patient_id());
+
+GRANT SELECT, INSERT, UPDATE ON televisit_sessions TO mpms_user;
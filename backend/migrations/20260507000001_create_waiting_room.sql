@@ -0,0 +1,107 @@
+-- Migration: Waiting room
+-- Date: 2026-05-07
+--
+-- Patients who arrive at the practice are marked ARRIVED. Arrival stamps
+-- checked_in_at and confirms the appointment; starting the visit
+-- (IN_PROGRESS) stamps visit_started_at and completing it stamps
+-- checked_out_at. The waiting room queue lists ARRIVED appointments, and the
+-- productivity report measures waits from checked_in_at to visit_started_at.
+
+-- ====================
+-- ARRIVED STATUS
+-- ====================
+
+ALTER TABLE appointments DROP CONSTRAINT IF EXISTS appointments_status_check;
+ALTER TABLE appointments ADD CONSTRAINT appointments_status_check CHECK (
+    status IN ('PENDING', 'SCHEDULED', 'CONFIRMED', 'ARRIVED', 'IN_PROGRESS', 'COMPLETED', 'CANCELLED',
+               'NO_SHOW')
+);
+
+COMMENT ON COLUMN appointments.status IS 'Appointment status: PENDING (booked online) → SCHEDULED → CONFIRMED → ARRIVED → IN_PROGRESS → COMPLETED (or CANCELLED/NO_SHOW)';
+
+-- ====================
+-- VISIT START
+-- ====================
+
+ALTER TABLE appointments ADD COLUMN IF NOT EXISTS visit_started_at TIMESTAMPTZ;
+
+COMMENT ON COLUMN appointments.checked_in_at IS 'When the patient arrived (status ARRIVED)';
+COMMENT ON COLUMN appointments.visit_started_at IS 'When the visit started (status IN_PROGRESS)';
+COMMENT ON COLUMN appointments.checked_out_at IS 'When the visit ended (status COMPLETED)';
+
+-- Waiting room queue: today's arrivals by scheduled time
+CREATE INDEX IF NOT EXISTS idx_appointments_arrived
+    ON appointments (scheduled_start, checked_in_at)
+    WHERE status = 'ARRIVED';
+
+-- ====================
+-- STATUS TRANSITIONS
+-- ====================
+
+CREATE OR REPLACE FUNCTION validate_appointment_status_transition()
+RETURNS TRIGGER AS $$
+BEGIN
+    -- PENDING can go to: SCHEDULED, CONFIRMED (accepted), CANCELLED (declined)
+    IF OLD.status = 'PENDING' AND NEW.status NOT IN ('PENDING', 'SCHEDULED', 'CONFIRMED', 'CANCELLED') THEN
+        RAISE EXCEPTION 'Invalid status transition from PENDING to %', NEW.status;
+    END IF;
+
+    -- SCHEDULED can go to: CONFIRMED, ARRIVED, CANCELLED, NO_SHOW
+    IF OLD.status = 'SCHEDULED' AND NEW.status NOT IN ('SCHEDULED', 'CONFIRMED', 'ARRIVED', 'CANCELLED', 'NO_SHOW') THEN
+        RAISE EXCEPTION 'Invalid status transition from SCHEDULED to %', NEW.status;
+    END IF;
+
+    -- CONFIRMED can go to: ARRIVED, IN_PROGRESS, CANCELLED, NO_SHOW
+    IF OLD.status = 'CONFIRMED' AND NEW.status NOT IN ('CONFIRMED', 'ARRIVED', 'IN_PROGRESS', 'CANCELLED', 'NO_SHOW') THEN
+        RAISE EXCEPTION 'Invalid status transition from CONFIRMED to %', NEW.status;
+    END IF;
+
+    -- ARRIVED can go to: IN_PROGRESS, CANCELLED, NO_SHOW (left before being seen)
+    IF OLD.status = 'ARRIVED' AND NEW.status NOT IN ('ARRIVED', 'IN_PROGRESS', 'CANCELLED', 'NO_SHOW') THEN
+        RAISE EXCEPTION 'Invalid status transition from ARRIVED to %', NEW.status;
+    END IF;
+
+    -- IN_PROGRESS can go to: COMPLETED, CANCELLED
+    IF OLD.status = 'IN_PROGRESS' AND NEW.status NOT IN ('IN_PROGRESS', 'COMPLETED', 'CANCELLED') THEN
+        RAISE EXCEPTION 'Invalid status transition from IN_PROGRESS to %', NEW.status;
+    END IF;
+
+    -- COMPLETED cannot be changed
+    IF OLD.status = 'COMPLETED' AND NEW.status != 'COMPLETED' THEN
+        RAISE EXCEPTION 'Cannot change status from COMPLETED';
+    END IF;
+
+    -- CANCELLED and NO_SHOW are final states
+    IF OLD.status IN ('CANCELLED', 'NO_SHOW') AND NEW.status != OLD.status THEN
+        RAISE EXCEPTION 'Cannot change status from % to %', OLD.status, NEW.status;
+    END IF;
+
+    -- Automatically set confirmed_at when moving to CONFIRMED, or to ARRIVED
+    -- straight from SCHEDULED (the patient turning up confirms it)
+    IF NEW.status IN ('CONFIRMED', 'ARRIVED') AND OLD.status != NEW.status AND NEW.confirmed_at IS NULL THEN
+        NEW.confirmed_at := NOW();
+    END IF;
+
+    -- Automatically set checked_in_at when moving to ARRIVED
+    IF NEW.status = 'ARRIVED' AND OLD.status != 'ARRIVED' AND NEW.checked_in_at IS NULL THEN
+        NEW.checked_in_at := NOW();
+    END IF;
+
+    -- Automatically set visit_started_at when moving to IN_PROGRESS
+    IF NEW.status = 'IN_PROGRESS' AND OLD.status != 'IN_PROGRESS' AND NEW.visit_started_at IS NULL THEN
+        NEW.visit_started_at := NOW();
+    END IF;
+
+    -- Automatically set checked_out_at when moving to COMPLETED
+    IF NEW.status = 'COMPLETED' AND OLD.status != 'COMPLETED' AND NEW.checked_out_at IS NULL THEN
+        NEW.checked_out_at := NOW();
+    END IF;
+
+    -- Automatically set cancelled_at when moving to CANCELLED
+    IF NEW.status = 'CANCELLED' AND OLD.status != 'CANCELLED' AND NEW.cancelled_at IS NULL THEN
+        NEW.cancelled_at := NOW();
+    END IF;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,267 @@
+-- Migration: Appointment waitlist
+-- Date: 2026-05-08
+--
+-- Patients who want an earlier appointment are put on a waitlist, for one
+-- provider or any, with the type and length of the appointment and the days
+-- they can come. When an appointment is cancelled while patients wait, the
+-- freed time becomes a waitlist slot. The waitlist job
+-- (services/waitlist_service.rs) offers each open slot to the matching
+-- patients, longest waiting first, a few at a time: each gets an email
+-- (WAITLIST_OFFER) with signed links to accept or decline. The first to
+-- accept gets the appointment and the other offers are superseded. When
+-- every offer of a slot is declined or has expired, the slot is offered to
+-- the next patients, until it is filled, taken by another booking or starts.
+
+-- ====================
+-- WAITLIST ENTRIES
+-- ====================
+
+CREATE TABLE IF NOT EXISTS waitlist_entries (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    patient_id UUID NOT NULL REFERENCES patients(id) ON DELETE CASCADE,
+    -- NULL: any provider
+    provider_id UUID REFERENCES users(id) ON DELETE CASCADE,
+    -- NULL: the type of the freed appointment
+    appointment_type VARCHAR(50),
+    duration_minutes INT NOT NULL DEFAULT 30 CHECK (duration_minutes BETWEEN 15 AND 480),
+    -- Days the patient can come (Europe/Rome), NULL: no limit
+    earliest_date DATE,
+    latest_date DATE,
+    status VARCHAR(20) NOT NULL DEFAULT 'WAITING'
+        CHECK (status IN ('WAITING', 'BOOKED', 'CANCELLED', 'EXPIRED')),
+    booked_appointment_id UUID REFERENCES appointments(id) ON DELETE SET NULL,
+    closed_at TIMESTAMPTZ,
+    created_by UUID NOT NULL REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT waitlist_entries_dates CHECK (
+        earliest_date IS NULL OR latest_date IS NULL OR earliest_date <= latest_date
+    ),
+    CONSTRAINT waitlist_entries_closed CHECK ((status = 'WAITING') = (closed_at IS NULL)),
+    CONSTRAINT waitlist_entries_booked CHECK (status = 'BOOKED' OR booked_appointment_id IS NULL)
+);
+
+-- Candidates are taken in order of arrival on the list
+CREATE INDEX IF NOT EXISTS idx_waitlist_entries_waiting
+    ON waitlist_entries (created_at)
+    WHERE status = 'WAITING';
+CREATE INDEX IF NOT EXISTS idx_waitlist_entries_patient
+    ON waitlist_entries (patient_id);
+
+COMMENT ON TABLE waitlist_entries IS 'Patients waiting for an earlier appointment, offered the slots freed by cancellations';
+COMMENT ON COLUMN waitlist_entries.status IS 'WAITING, BOOKED (accepted an offer), CANCELLED (by staff), EXPIRED (latest_date passed)';
+
+CREATE TRIGGER update_waitlist_entries_updated_at
+    BEFORE UPDATE ON waitlist_entries
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
+
+-- No new entries for erased or merged patients
+CREATE TRIGGER trigger_prevent_anonymized_waitlist_entry
+    BEFORE INSERT ON waitlist_entries
+    FOR EACH ROW
+    EXECUTE FUNCTION prevent_anonymized_patient_reference();
+
+-- ====================
+-- SLOTS
+-- ====================
+
+CREATE TABLE IF NOT EXISTS waitlist_slots (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    cancelled_appointment_id UUID NOT NULL UNIQUE REFERENCES appointments(id) ON DELETE CASCADE,
+    provider_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    appointment_type VARCHAR(50) NOT NULL,
+    slot_start TIMESTAMPTZ NOT NULL,
+    slot_end TIMESTAMPTZ NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'OPEN' CHECK (status IN ('OPEN', 'FILLED', 'CLOSED')),
+    filled_appointment_id UUID REFERENCES appointments(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    closed_at TIMESTAMPTZ,
+
+    CONSTRAINT waitlist_slots_closed CHECK ((status = 'OPEN') = (closed_at IS NULL))
+);
+
+CREATE INDEX IF NOT EXISTS idx_waitlist_slots_open
+    ON waitlist_slots (slot_start)
+    WHERE status = 'OPEN';
+
+COMMENT ON TABLE waitlist_slots IS 'Time freed by cancelled appointments, offered to waitlisted patients';
+COMMENT ON COLUMN waitlist_slots.status IS 'OPEN (being offered), FILLED (accepted), CLOSED (started or taken by another booking)';
+
+-- A cancelled future appointment frees its time for the waitlist, when
+-- patients wait. SECURITY DEFINER: cancellations from a reminder link run as
+-- the patient, who may not write waitlist slots.
+CREATE OR REPLACE FUNCTION open_waitlist_slot()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF NEW.status = 'CANCELLED' AND OLD.status <> 'CANCELLED'
+       AND NEW.scheduled_start > NOW()
+       AND EXISTS (
+           SELECT 1 FROM waitlist_entries w
+           WHERE w.status = 'WAITING'
+             AND (w.provider_id IS NULL OR w.provider_id = NEW.provider_id)
+       ) THEN
+        INSERT INTO waitlist_slots (
+            cancelled_appointment_id, provider_id, appointment_type, slot_start, slot_end
+        )
+        VALUES (NEW.id, NEW.provider_id, NEW.type, NEW.scheduled_start, NEW.scheduled_end)
+        ON CONFLICT (cancelled_appointment_id) DO NOTHING;
+    END IF;
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql SECURITY DEFINER SET search_path = public;
+
+CREATE TRIGGER trigger_open_waitlist_slot
+    AFTER UPDATE OF status ON appointments
+    FOR EACH ROW
+    EXECUTE FUNCTION open_waitlist_slot();
+
+-- ====================
+-- OFFERS
+-- ====================
+
+CREATE TABLE IF NOT EXISTS waitlist_offers (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    slot_id UUID NOT NULL REFERENCES waitlist_slots(id) ON DELETE CASCADE,
+    waitlist_entry_id UUID NOT NULL REFERENCES waitlist_entries(id) ON DELETE CASCADE,
+    patient_id UUID NOT NULL REFERENCES patients(id) ON DELETE CASCADE,
+    notification_id UUID REFERENCES notification_queue(id) ON DELETE SET NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'PENDING'
+        CHECK (status IN ('PENDING', 'ACCEPTED', 'DECLINED', 'EXPIRED', 'SUPERSEDED')),
+    expires_at TIMESTAMPTZ NOT NULL,
+    responded_at TIMESTAMPTZ,
+    appointment_id UUID REFERENCES appointments(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    -- A slot is offered to a patient once
+    CONSTRAINT waitlist_offers_once UNIQUE (slot_id, waitlist_entry_id),
+    CONSTRAINT waitlist_offers_accepted CHECK ((status = 'ACCEPTED') = (appointment_id IS NOT NULL))
+);
+
+CREATE INDEX IF NOT EXISTS idx_waitlist_offers_pending
+    ON waitlist_offers (expires_at)
+    WHERE status = 'PENDING';
+CREATE INDEX IF NOT EXISTS idx_waitlist_offers_entry
+    ON waitlist_offers (waitlist_entry_id, created_at DESC);
+
+COMMENT ON TABLE waitlist_offers IS 'Slots offered to waitlisted patients and their answer';
+COMMENT ON COLUMN waitlist_offers.status IS 'PENDING, ACCEPTED, DECLINED, EXPIRED (no answer in time), SUPERSEDED (slot filled or gone)';
+
+-- ====================
+-- NOTIFICATION TYPE
+-- ====================
+
+ALTER TABLE notification_queue
+    DROP CONSTRAINT IF EXISTS notification_queue_notification_type_check;
+ALTER TABLE notification_queue
+    ADD CONSTRAINT notification_queue_notification_type_check CHECK (
+        notification_type IN ('APPOINTMENT_REMINDER', 'APPOINTMENT_BOOKED', 'APPOINTMENT_CONFIRMATION',
+                              'APPOINTMENT_CANCELLATION', 'VISIT_SUMMARY', 'PRESCRIPTION_READY',
+                              'FOLLOW_UP_REMINDER', 'CUSTOM', 'MARKETING', 'REFERRAL_REMINDER',
+                              'REFILL_REMINDER', 'BIRTHDAY_GREETING', 'SCREENING_REMINDER',
+                              'PATIENT_CANCELLATION', 'VISIT_SURVEY', 'TELEVISIT_INVITATION',
+                              'WAITLIST_OFFER')
+    );
+
+-- ====================
+-- RLS
+-- ====================
+
+ALTER TABLE waitlist_entries ENABLE ROW LEVEL SECURITY;
+ALTER TABLE waitlist_entries FORCE ROW LEVEL SECURITY;
+ALTER TABLE waitlist_slots ENABLE ROW LEVEL SECURITY;
+ALTER TABLE waitlist_slots FORCE ROW LEVEL SECURITY;
+ALTER TABLE waitlist_offers ENABLE ROW LEVEL SECURITY;
+ALTER TABLE waitlist_offers FORCE ROW LEVEL SECURITY;
+
+-- Staff reach an entry through its patient, so the patient policies apply
+-- here too
+CREATE POLICY waitlist_entries_select_policy ON waitlist_entries
+    FOR SELECT
+    USING (
+        (is_doctor() OR is_nurse())
+        AND EXISTS (SELECT 1 FROM patients p WHERE p.id = waitlist_entries.patient_id)
+    );
+
+CREATE POLICY waitlist_entries_insert_policy ON waitlist_entries
+    FOR INSERT
+    WITH CHECK (
+        (is_doctor() OR is_nurse())
+        AND EXISTS (SELECT 1 FROM patients p WHERE p.id = waitlist_entries.patient_id)
+    );
+
+CREATE POLICY waitlist_entries_update_policy ON waitlist_entries
+    FOR UPDATE
+    USING (
+        (is_doctor() OR is_nurse())
+        AND EXISTS (SELECT 1 FROM patients p WHERE p.id = waitlist_entries.patient_id)
+    )
+    WITH CHECK (
+        (is_doctor() OR is_nurse())
+        AND EXISTS (SELECT 1 FROM patients p WHERE p.id = waitlist_entries.patient_id)
+    );
+
+-- Slots are opened by the trigger above and worked by the waitlist job
+CREATE POLICY waitlist_slots_select_policy ON waitlist_slots
+    FOR SELECT
+    USING (is_doctor() OR is_nurse());
+
+CREATE POLICY waitlist_slots_update_policy ON waitlist_slots
+    FOR UPDATE
+    USING (is_admin())
+    WITH CHECK (is_admin());
+
+CREATE POLICY waitlist_offers_select_policy ON waitlist_offers
+    FOR SELECT
+    USING (is_doctor() OR is_nurse());
+
+-- Offers are made and answered by the waitlist job and the offer links;
+-- staff supersede them when cancelling an entry
+CREATE POLICY waitlist_offers_insert_policy ON waitlist_offers
+    FOR INSERT
+    WITH CHECK (is_admin());
+
+CREATE POLICY waitlist_offers_update_policy ON waitlist_offers
+    FOR UPDATE
+    USING (is_doctor() OR is_nurse())
+    WITH CHECK (is_doctor() OR is_nurse());
+
+GRANT SELECT, INSERT, UPDATE ON waitlist_entries TO mpms_user;
+GRANT SELECT, INSERT, UPDATE ON waitlist_slots TO mpms_user;
+GRANT SELECT, INSERT, UPDATE ON waitlist_offers TO mpms_user;
+
+-- ====================
+-- SETTINGS
+-- ====================
+
+INSERT INTO system_settings (
+    setting_key, setting_group, setting_name, setting_value,
+    value_type, description, default_value, is_public, is_encrypted, is_readonly
+) VALUES
+(
+    'waitlist.offer_expiry_minutes',
+    'waitlist',
+    'Waitlist Offer Expiry (minutes)',
+    '120',
+    'INTEGER',
+    'How long a waitlisted patient has to accept a freed slot before it is offered to the next patients. Offers always expire before the slot starts.',
+    '120',
+    false,
+    false,
+    false
+),
+(
+    'waitlist.offers_per_round',
+    'waitlist',
+    'Waitlist Offers per Round',
+    '3',
+    'INTEGER',
+    'How many waitlisted patients are offered a freed slot at the same time. The first to accept gets it.',
+    '3',
+    false,
+    false,
+    false
+)
+ON CONFLICT (setting_key) DO NOTHING;
@@ -0,0 +1,28 @@
+-- Migration: Buffer times per appointment type
+-- Date: 2026-05-09
+--
+-- Some appointments need preparation or clean-up time of their own (e.g. an
+-- acupuncture session that blocks the room for 10 minutes before and 5
+-- after). The appointment.type_buffers setting holds the minutes before and
+-- after each appointment type; types without an entry have none. Buffers
+-- count in conflict detection and availability, so a 30-minute session with
+-- a 10/5 buffer keeps the provider busy for 45 minutes. Appointments may
+-- still be back to back when neither has a buffer.
+
+INSERT INTO system_settings (
+    setting_key, setting_group, setting_name, setting_value,
+    value_type, description, default_value, is_public, is_encrypted, is_readonly
+) VALUES
+(
+    'appointment.type_buffers',
+    'appointment',
+    'Buffer Times per Appointment Type',
+    '{}',
+    'JSON',
+    'Preparation (before) and clean-up (after) minutes per appointment type, e.g. {"ACUPUNCTURE": {"before": 10, "after": 5}}. Each value is 0-120.',
+    '{}',
+    false,
+    false,
+    false
+)
+ON CONFLICT (setting_key) DO NOTHING;
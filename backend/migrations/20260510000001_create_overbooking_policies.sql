@@ -0,0 +1,55 @@
+-- Migration: Provider overbooking policies
+-- Date: 2026-05-10
+--
+-- An overbooking policy lets a provider's calendar take appointments over
+-- others already booked: at most max_parallel appointments at the same
+-- time, optionally only for some appointment types and within some hours
+-- of the day (Europe/Rome). Providers without a policy cannot be
+-- overbooked. Overbooking is an explicit override: the booking asks for it
+-- (overbook: true), the user needs the 'overbook' permission on
+-- appointments, and the audit entry of the appointment lists the
+-- appointments it was booked over.
+
+CREATE TABLE IF NOT EXISTS provider_overbooking_policies (
+    provider_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    max_parallel INT NOT NULL CHECK (max_parallel BETWEEN 2 AND 5),
+    -- Types that may be overbooked; empty: any
+    appointment_types VARCHAR(50)[] NOT NULL DEFAULT '{}',
+    -- NULL: the whole day
+    start_time TIME,
+    end_time TIME,
+    created_by UUID REFERENCES users(id),
+    updated_by UUID REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT provider_overbooking_policies_hours CHECK (
+        (start_time IS NULL AND end_time IS NULL)
+        OR (start_time IS NOT NULL AND end_time IS NOT NULL AND start_time < end_time)
+    )
+);
+
+CREATE TRIGGER update_provider_overbooking_policies_updated_at
+    BEFORE UPDATE ON provider_overbooking_policies
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
+
+COMMENT ON TABLE provider_overbooking_policies IS 'Providers whose calendar may be overbooked, and how far';
+COMMENT ON COLUMN provider_overbooking_policies.max_parallel IS 'Most appointments of the provider at the same time, the overbooked one included';
+
+GRANT SELECT, INSERT, UPDATE, DELETE ON provider_overbooking_policies TO mpms_user;
+
+-- ====================
+-- CASBIN POLICIES
+-- ====================
+-- Same rules as casbin/policy.csv, for installs that already seeded the table
+
+INSERT INTO casbin_rules (ptype, v0, v1, v2)
+SELECT v.ptype, v.v0, v.v1, v.v2
+FROM (VALUES
+    ('p', 'ADMIN', 'appointments', 'overbook'),
+    ('p', 'ADMIN', 'appointments', 'manage_overbooking'),
+    ('p', 'DOCTOR', 'appointments', 'overbook')
+) AS v (ptype, v0, v1, v2)
+WHERE EXISTS (SELECT 1 FROM casbin_rules)
+ON CONFLICT (ptype, v0, v1, v2, v3, v4, v5) DO NOTHING;
@@ -0,0 +1,103 @@
+-- Migration: Bookable resources
+-- Date: 2026-05-11
+--
+-- Rooms and equipment (an ECG machine, an ultrasound scanner) that
+-- appointments reserve. A resource is booked by one appointment at a time:
+-- creating or moving an appointment checks the calendars of its resources
+-- as well as the provider's, and the availability check reports which
+-- resource keeps a time from being free. Resources are deactivated rather
+-- than deleted, so that past appointments keep their reservations.
+
+-- ====================
+-- RESOURCES
+-- ====================
+
+CREATE TABLE IF NOT EXISTS resources (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(100) NOT NULL,
+    resource_type VARCHAR(20) NOT NULL CHECK (resource_type IN ('ROOM', 'EQUIPMENT')),
+    description TEXT,
+    is_active BOOLEAN NOT NULL DEFAULT true,
+    created_by UUID REFERENCES users(id),
+    updated_by UUID REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_resources_name ON resources (LOWER(name));
+
+CREATE TRIGGER update_resources_updated_at
+    BEFORE UPDATE ON resources
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
+
+COMMENT ON TABLE resources IS 'Rooms and equipment appointments can reserve';
+COMMENT ON COLUMN resources.is_active IS 'Inactive resources keep their reservations but cannot be booked';
+
+-- ====================
+-- RESERVATIONS
+-- ====================
+
+CREATE TABLE IF NOT EXISTS appointment_resources (
+    appointment_id UUID NOT NULL REFERENCES appointments(id) ON DELETE CASCADE,
+    resource_id UUID NOT NULL REFERENCES resources(id),
+    PRIMARY KEY (appointment_id, resource_id)
+);
+
+-- Calendar of a resource
+CREATE INDEX IF NOT EXISTS idx_appointment_resources_resource
+    ON appointment_resources (resource_id);
+
+COMMENT ON TABLE appointment_resources IS 'Resources reserved by an appointment for its whole time';
+
+-- ====================
+-- RESOURCE CALENDARS
+-- ====================
+-- A resource is shared by every provider, but a doctor only sees their own
+-- appointments under RLS, so the bookings of a resource come from this
+-- function: the appointment and its times only, nothing about the patient
+-- or the visit.
+
+CREATE OR REPLACE FUNCTION resource_busy_intervals(
+    p_resource_ids UUID[],
+    p_from TIMESTAMPTZ,
+    p_to TIMESTAMPTZ,
+    p_exclude_appointment_id UUID
+)
+RETURNS TABLE (
+    resource_id UUID,
+    appointment_id UUID,
+    scheduled_start TIMESTAMPTZ,
+    scheduled_end TIMESTAMPTZ
+)
+SECURITY DEFINER
+SET search_path = public
+AS $$
+    SELECT ar.resource_id, a.id, a.scheduled_start, a.scheduled_end
+    FROM appointment_resources ar
+    JOIN appointments a ON a.id = ar.appointment_id
+    WHERE ar.resource_id = ANY(p_resource_ids)
+      AND (p_exclude_appointment_id IS NULL OR a.id != p_exclude_appointment_id)
+      AND a.status NOT IN ('CANCELLED', 'NO_SHOW')
+      AND tstzrange(a.scheduled_start, a.scheduled_end) && tstzrange(p_from, p_to)
+    ORDER BY a.scheduled_start
+$$ LANGUAGE sql STABLE;
+
+REVOKE ALL ON FUNCTION resource_busy_intervals(UUID[], TIMESTAMPTZ, TIMESTAMPTZ, UUID) FROM PUBLIC;
+GRANT EXECUTE ON FUNCTION resource_busy_intervals(UUID[], TIMESTAMPTZ, TIMESTAMPTZ, UUID) TO mpms_user;
+
+GRANT SELECT, INSERT, UPDATE, DELETE ON resources TO mpms_user;
+GRANT SELECT, INSERT, UPDATE, DELETE ON appointment_resources TO mpms_user;
+
+-- ====================
+-- CASBIN POLICIES
+-- ====================
+-- Same rules as casbin/policy.csv, for installs that already seeded the table
+
+INSERT INTO casbin_rules (ptype, v0, v1, v2)
+SELECT v.ptype, v.v0, v.v1, v.v2
+FROM (VALUES
+    ('p', 'ADMIN', 'appointments', 'manage_resources')
+) AS v (ptype, v0, v1, v2)
+WHERE EXISTS (SELECT 1 FROM casbin_rules)
+ON CONFLICT (ptype, v0, v1, v2, v3, v4, v5) DO NOTHING;
@@ -0,0 +1,30 @@
+-- Migration: Provider calendar feeds
+-- Date: 2026-05-12
+--
+-- A provider subscribes their phone or desktop calendar to an iCalendar
+-- feed of their appointments at /api/v1/calendar/{token}.ics. Calendar
+-- apps cannot send a JWT, so the random token in the URL is the only
+-- credential: only its SHA-256 hash is stored, creating the feed again
+-- replaces it (the old URL stops working) and deleting it revokes it.
+--
+-- The privacy mode decides what the feed shows: FULL lists the patient,
+-- type and reason of each appointment, BUSY_ONLY only when the provider is
+-- busy. The feed is read with the provider's own RLS context, so it never
+-- shows more than the provider can see in the application.
+
+CREATE TABLE IF NOT EXISTS calendar_feeds (
+    provider_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    privacy_mode VARCHAR(20) NOT NULL DEFAULT 'BUSY_ONLY'
+        CHECK (privacy_mode IN ('FULL', 'BUSY_ONLY')),
+    last_accessed_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+COMMENT ON TABLE calendar_feeds IS 'Tokenized read-only iCalendar feeds of providers'' appointments';
+COMMENT ON COLUMN calendar_feeds.token_hash IS 'SHA-256 (hex) of the token in the feed URL';
+COMMENT ON COLUMN calendar_feeds.privacy_mode IS 'FULL: patient, type and reason; BUSY_ONLY: busy times only';
+COMMENT ON COLUMN calendar_feeds.updated_at IS 'Last change of the token or privacy mode (not bumped by reads of the feed)';
+
+GRANT SELECT, INSERT, UPDATE, DELETE ON calendar_feeds TO mpms_user;
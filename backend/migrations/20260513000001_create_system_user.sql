@@ -0,0 +1,28 @@
+-- ==============================================================================
+-- Create System User
+--
+-- Dedicated account that background jobs (notification scheduler, janitor,
+-- retention purge, OCR, search indexing, ...) act as: their transactions run
+-- with its RLS context and their changes are recorded under its id
+-- (db::rls::SYSTEM_USER_ID in the backend).
+--
+-- It cannot sign in: the account is inactive and its password hash matches no
+-- password.
+--
+-- Uses ON CONFLICT (id) DO NOTHING for idempotent re-runs.
+-- ==============================================================================
+
+INSERT INTO users (
+    id, username, email, password_hash, role,
+    first_name, last_name, is_active
+) VALUES (
+    '00000000-0000-0000-0000-000000000001',
+    'system',
+    'system@docpat.invalid',
+    '!',
+    'ADMIN',
+    'System',
+    'DocPat',
+    false
+)
+ON CONFLICT (id) DO NOTHING;
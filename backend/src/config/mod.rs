@@ -5,8 +5,16 @@
  * and provides structured access to configuration values.
  */
 
+use ipnetwork::IpNetwork;
+use std::sync::Arc;
 use std::time::Duration;
 
+use crate::utils::jwt_keys::{JwtAlgorithm, JwtKeyRing};
+
+pub mod secrets;
+
+pub use secrets::{secret_provider_from_env, ConfigSecrets, SecretProvider};
+
 /// Application configuration loaded from environment variables
 #[derive(Debug, Clone)]
 pub struct Config {
@@ -22,6 +30,20 @@ pub struct Config {
     pub email: Option<EmailConfig>,
     /// TLS/HTTPS configuration
     pub tls: TlsConfig,
+    /// CIDR ranges allowed to reach admin routes (empty = no restriction)
+    pub admin_ip_allowlist: Vec<IpNetwork>,
+    /// Reverse proxies whose X-Forwarded-For / X-Real-IP headers are trusted
+    pub trusted_proxies: Vec<IpNetwork>,
+    /// Security event streaming to a SIEM (None = disabled)
+    pub siem: Option<SiemConfig>,
+    /// Text recognition of patient attachments (None = disabled)
+    pub ocr: Option<OcrConfig>,
+    /// Transcription of dictated visit audio memos (None = disabled)
+    pub transcription: Option<TranscriptionConfig>,
+    /// Video rooms for televisits (None = disabled)
+    pub video: Option<VideoConfig>,
+    /// Sistema TS dematerialized prescriptions (None = disabled)
+    pub sistema_ts: Option<SistemaTsConfig>,
 }
 
 /// TLS/HTTPS configuration for secure connections
@@ -138,20 +160,23 @@ pub struct DatabaseConfig {
 /// JWT configuration
 #[derive(Debug, Clone)]
 pub struct JwtConfig {
-    /// JWT secret key for signing access tokens
+    /// JWT secret key for signing access tokens (from the SECRET_PROVIDER)
     pub secret: String,
-    /// JWT refresh token secret key
+    /// JWT refresh token secret key (from the SECRET_PROVIDER)
     pub refresh_secret: String,
     /// Access token expiration time in seconds (default: 30 minutes)
     pub access_token_expiry: i64,
     /// Refresh token expiration time in seconds (default: 7 days)
     pub refresh_token_expiry: i64,
+    /// Asymmetric keys for access tokens (RS256/EdDSA); None signs with `secret` (HS256)
+    pub key_ring: Option<Arc<JwtKeyRing>>,
 }
 
 /// Security configuration
 #[derive(Debug, Clone)]
 pub struct SecurityConfig {
-    /// AES-256 encryption key for medical data (32 bytes base64)
+    /// AES-256 encryption key for medical data (32 bytes base64), from the
+    /// SECRET_PROVIDER
     pub encryption_key: String,
     /// Session timeout in seconds (default: 30 minutes)
     pub session_timeout: i64,
@@ -159,9 +184,386 @@ pub struct SecurityConfig {
     pub max_failed_login_attempts: u32,
     /// Account lockout duration in seconds
     pub lockout_duration: i64,
+    /// CAPTCHA challenge after repeated failed logins (None = disabled)
+    pub captcha: Option<CaptchaConfig>,
+}
+
+/// CAPTCHA provider used for login challenges
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptchaProvider {
+    /// hCaptcha (https://www.hcaptcha.com)
+    HCaptcha,
+    /// Cloudflare Turnstile
+    Turnstile,
+}
+
+impl CaptchaProvider {
+    /// Parse a provider name (case-insensitive)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "hcaptcha" => Some(Self::HCaptcha),
+            "turnstile" => Some(Self::Turnstile),
+            _ => None,
+        }
+    }
+
+    /// Provider name as exposed to clients
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::HCaptcha => "hcaptcha",
+            Self::Turnstile => "turnstile",
+        }
+    }
+
+    /// Server-side token verification endpoint
+    pub fn verify_url(&self) -> &'static str {
+        match self {
+            Self::HCaptcha => "https://api.hcaptcha.com/siteverify",
+            Self::Turnstile => "https://challenges.cloudflare.com/turnstile/v0/siteverify",
+        }
+    }
+}
+
+/// CAPTCHA configuration for login challenges
+#[derive(Debug, Clone)]
+pub struct CaptchaConfig {
+    /// CAPTCHA provider
+    pub provider: CaptchaProvider,
+    /// Public site key rendered by the frontend widget
+    pub site_key: String,
+    /// Secret key for server-side verification
+    pub secret_key: String,
+    /// Failed logins from the same IP or against the same username before a
+    /// CAPTCHA is required (default: 3)
+    pub failure_threshold: usize,
+}
+
+/// How security events reach the SIEM
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SiemTransport {
+    /// Syslog over UDP, one datagram per event
+    Udp(String),
+    /// Syslog over TCP, newline-framed (RFC 6587)
+    Tcp(String),
+    /// HTTP(S) POST of batched events (e.g. a SIEM HTTP collector)
+    Http(String),
+}
+
+impl SiemTransport {
+    /// Parse an endpoint URL: udp://host:port, tcp://host:port or http(s)://...
+    pub fn from_endpoint(endpoint: &str) -> Option<Self> {
+        let endpoint = endpoint.trim();
+        if let Some(addr) = endpoint.strip_prefix("udp://") {
+            Some(Self::Udp(addr.to_string()))
+        } else if let Some(addr) = endpoint.strip_prefix("tcp://") {
+            Some(Self::Tcp(addr.to_string()))
+        } else if endpoint.starts_with("https://") || endpoint.starts_with("http://") {
+            Some(Self::Http(endpoint.to_string()))
+        } else {
+            None
+        }
+    }
+}
+
+/// Wire format of streamed security events
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SiemFormat {
+    /// ArcSight Common Event Format inside an RFC 5424 syslog message
+    Cef,
+    /// One JSON object per event
+    Json,
+}
+
+impl SiemFormat {
+    /// Parse a format name (case-insensitive)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "cef" => Some(Self::Cef),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+}
+
+/// SIEM streaming configuration
+#[derive(Clone)]
+pub struct SiemConfig {
+    /// Where events are sent
+    pub transport: SiemTransport,
+    /// Event encoding
+    pub format: SiemFormat,
+    /// Bearer token for HTTP endpoints
+    /// SECURITY: This is sensitive - never log or store this value
+    auth_token: Option<String>,
+    /// Events buffered in memory while the SIEM is slow or down (default: 10000)
+    pub buffer_size: usize,
+    /// Hostname reported in syslog headers
+    pub hostname: String,
+}
+
+impl SiemConfig {
+    /// Get the HTTP bearer token
+    pub fn auth_token(&self) -> Option<&str> {
+        self.auth_token.as_deref()
+    }
+}
+
+// Custom Debug implementation to prevent token leakage in logs
+impl std::fmt::Debug for SiemConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SiemConfig")
+            .field("transport", &self.transport)
+            .field("format", &self.format)
+            .field("auth_token", &self.auth_token.as_ref().map(|_| "[REDACTED]"))
+            .field("buffer_size", &self.buffer_size)
+            .field("hostname", &self.hostname)
+            .finish()
+    }
+}
+
+/// Engine that recognizes text in patient attachments
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OcrProvider {
+    /// Local Tesseract binary (images only)
+    Tesseract,
+    /// External OCR service: the file is POSTed to this URL
+    Http(String),
+}
+
+/// OCR configuration for patient attachments
+#[derive(Clone)]
+pub struct OcrConfig {
+    pub provider: OcrProvider,
+    /// Path of the tesseract binary (default: `tesseract` on PATH)
+    pub tesseract_path: String,
+    /// Tesseract language codes (default: `ita+eng`)
+    pub languages: String,
+    /// Bearer token for the external service
+    /// SECURITY: This is sensitive - never log or store this value
+    auth_token: Option<String>,
+    /// Time allowed to recognize a single file (default: 120 seconds)
+    pub timeout: Duration,
+}
+
+impl OcrConfig {
+    /// Get the external service bearer token
+    pub fn auth_token(&self) -> Option<&str> {
+        self.auth_token.as_deref()
+    }
+}
+
+// Custom Debug implementation to prevent token leakage in logs
+impl std::fmt::Debug for OcrConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OcrConfig")
+            .field("provider", &self.provider)
+            .field("tesseract_path", &self.tesseract_path)
+            .field("languages", &self.languages)
+            .field("auth_token", &self.auth_token.as_ref().map(|_| "[REDACTED]"))
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+/// Engine that transcribes dictated audio memos
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TranscriptionProvider {
+    /// Local whisper.cpp binary with this model file (WAV and MP3 only)
+    Whisper(String),
+    /// External speech-to-text service: the audio is POSTed to this URL
+    Http(String),
+}
+
+/// Transcription configuration for visit audio memos
+#[derive(Clone)]
+pub struct TranscriptionConfig {
+    pub provider: TranscriptionProvider,
+    /// Path of the whisper.cpp binary (default: `whisper-cli` on PATH)
+    pub whisper_path: String,
+    /// Spoken language code (default: `it`)
+    pub language: String,
+    /// Bearer token for the external service
+    /// SECURITY: This is sensitive - never log or store this value
+    auth_token: Option<String>,
+    /// Time allowed to transcribe a single memo (default: 600 seconds)
+    pub timeout: Duration,
+}
+
+impl TranscriptionConfig {
+    /// Get the external service bearer token
+    pub fn auth_token(&self) -> Option<&str> {
+        self.auth_token.as_deref()
+    }
+}
+
+// Custom Debug implementation to prevent token leakage in logs
+impl std::fmt::Debug for TranscriptionConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TranscriptionConfig")
+            .field("provider", &self.provider)
+            .field("whisper_path", &self.whisper_path)
+            .field("language", &self.language)
+            .field("auth_token", &self.auth_token.as_ref().map(|_| "[REDACTED]"))
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+/// Service that provides video rooms for televisits
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VideoProvider {
+    /// Jitsi Meet server at this URL; rooms need no provisioning call
+    Jitsi(String),
+    /// External video service: room requests are POSTed to this URL
+    Http(String),
+}
+
+/// Video room configuration for televisits
+#[derive(Clone)]
+pub struct VideoConfig {
+    pub provider: VideoProvider,
+    /// Bearer token for the external service
+    /// SECURITY: This is sensitive - never log or store this value
+    auth_token: Option<String>,
+    /// Time allowed to provision a room (default: 15 seconds)
+    pub timeout: Duration,
+}
+
+impl VideoConfig {
+    /// Get the external service bearer token
+    pub fn auth_token(&self) -> Option<&str> {
+        self.auth_token.as_deref()
+    }
+}
+
+#[cfg(test)]
+impl VideoConfig {
+    /// Configuration without a token, for tests
+    pub(crate) fn test_config(provider: VideoProvider) -> Self {
+        Self {
+            provider,
+            auth_token: None,
+            timeout: Duration::from_secs(15),
+        }
+    }
+}
+
+// Custom Debug implementation to prevent token leakage in logs
+impl std::fmt::Debug for VideoConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VideoConfig")
+            .field("provider", &self.provider)
+            .field("auth_token", &self.auth_token.as_ref().map(|_| "[REDACTED]"))
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+/// Sistema TS dematerialized prescription (ricetta dematerializzata) configuration
+///
+/// One prescriber account: credentials are issued by Sogei per doctor.
+#[derive(Clone)]
+pub struct SistemaTsConfig {
+    /// Base URL of the prescription web services, e.g.
+    /// `https://demservice.sanita.finanze.it/DemRicettaPrescrittoServicesWeb/services`
+    pub endpoint: String,
+    pub username: String,
+    /// SECURITY: This is sensitive - never log or store this value
+    password: String,
+    /// Prescriber PIN code, sent encrypted with the Sistema TS public key
+    /// SECURITY: This is sensitive - never log or store this value
+    pincode: String,
+    /// Prescriber fiscal code (cfMedico)
+    pub prescriber_fiscal_code: String,
+    /// Region code (codRegione, e.g. `120` for Lazio)
+    pub region_code: String,
+    /// Local health authority code (codASLAo)
+    pub asl_code: String,
+    /// Prescriber specialization (codSpecializzazione, default `P`)
+    pub specialization: String,
+    /// PEM public key of the Sistema TS certificate (SanitelCF)
+    pub public_key_pem: String,
+    /// Shared secret expected in the X-Callback-Secret header of status callbacks
+    /// SECURITY: This is sensitive - never log or store this value
+    callback_secret: Option<String>,
+    /// Time allowed for each web service call (default: 30 seconds)
+    pub timeout: Duration,
+}
+
+impl SistemaTsConfig {
+    /// Get the web service password
+    pub fn password(&self) -> &str {
+        &self.password
+    }
+
+    /// Get the prescriber PIN code
+    pub fn pincode(&self) -> &str {
+        &self.pincode
+    }
+
+    /// Get the status callback secret
+    pub fn callback_secret(&self) -> Option<&str> {
+        self.callback_secret.as_deref()
+    }
+}
+
+#[cfg(test)]
+impl SistemaTsConfig {
+    /// Configuration with placeholder credentials, for tests
+    pub(crate) fn test_config() -> Self {
+        Self {
+            endpoint: "https://demservicetest.sanita.finanze.it/DemRicettaPrescrittoServicesWeb/services"
+                .to_string(),
+            username: "prescriber".to_string(),
+            password: "sistema-ts-password".to_string(),
+            pincode: "1234567890".to_string(),
+            prescriber_fiscal_code: "BNCGVN70A01F205X".to_string(),
+            region_code: "120".to_string(),
+            asl_code: "201".to_string(),
+            specialization: "P".to_string(),
+            public_key_pem: String::new(),
+            callback_secret: Some("callback-secret".to_string()),
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+// Custom Debug implementation to prevent credential leakage in logs
+impl std::fmt::Debug for SistemaTsConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SistemaTsConfig")
+            .field("endpoint", &self.endpoint)
+            .field("username", &self.username)
+            .field("password", &"[REDACTED]")
+            .field("pincode", &"[REDACTED]")
+            .field("prescriber_fiscal_code", &self.prescriber_fiscal_code)
+            .field("region_code", &self.region_code)
+            .field("asl_code", &self.asl_code)
+            .field("specialization", &self.specialization)
+            .field("callback_secret", &self.callback_secret.as_ref().map(|_| "[REDACTED]"))
+            .field("timeout", &self.timeout)
+            .finish()
+    }
 }
 
 impl Config {
+    /// Load configuration, key material from the SECRET_PROVIDER and the
+    /// rest from environment variables
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the secrets cannot be fetched, or required
+    /// environment variables are missing or contain invalid values.
+    pub async fn load() -> anyhow::Result<Self> {
+        dotenvy::dotenv().ok();
+
+        let provider = secret_provider_from_env()?;
+        let secrets = ConfigSecrets::fetch(provider.as_ref()).await?;
+        tracing::info!("Key material loaded from {} secrets", provider.name());
+
+        Self::with_secrets(secrets)
+    }
+
     /// Load configuration from environment variables
     ///
     /// # Errors
@@ -171,6 +573,10 @@ impl Config {
     pub fn from_env() -> anyhow::Result<Self> {
         dotenvy::dotenv().ok();
 
+        Self::with_secrets(ConfigSecrets::from_env()?)
+    }
+
+    fn with_secrets(secrets: ConfigSecrets) -> anyhow::Result<Self> {
         let config = Self {
             server: ServerConfig {
                 host: std::env::var("SERVER_HOST").unwrap_or_else(|_| "0.0.0.0".to_string()),
@@ -214,10 +620,8 @@ impl Config {
             },
 
             jwt: JwtConfig {
-                secret: std::env::var("JWT_SECRET")
-                    .expect("JWT_SECRET must be set"),
-                refresh_secret: std::env::var("JWT_REFRESH_SECRET")
-                    .expect("JWT_REFRESH_SECRET must be set"),
+                secret: secrets.jwt_secret,
+                refresh_secret: secrets.jwt_refresh_secret,
                 access_token_expiry: std::env::var("JWT_ACCESS_TOKEN_EXPIRY")
                     .unwrap_or_else(|_| "900".to_string()) // 15 minutes (aligned with .env.example)
                     .parse()
@@ -226,11 +630,11 @@ impl Config {
                     .unwrap_or_else(|_| "604800".to_string()) // 7 days
                     .parse()
                     .unwrap_or(604800),
+                key_ring: Self::load_jwt_key_ring()?,
             },
 
             security: SecurityConfig {
-                encryption_key: std::env::var("ENCRYPTION_KEY")
-                    .expect("ENCRYPTION_KEY must be set"),
+                encryption_key: secrets.encryption_key,
                 session_timeout: std::env::var("SESSION_TIMEOUT")
                     .unwrap_or_else(|_| "1800".to_string())
                     .parse()
@@ -243,16 +647,423 @@ impl Config {
                     .unwrap_or_else(|_| "900".to_string()) // 15 minutes
                     .parse()
                     .unwrap_or(900),
+                captcha: Self::load_captcha_config()?,
             },
 
             email: Self::load_email_config(),
 
             tls: Self::load_tls_config(),
+
+            admin_ip_allowlist: Self::load_admin_ip_allowlist()?,
+
+            trusted_proxies: Self::load_trusted_proxies()?,
+
+            siem: Self::load_siem_config()?,
+
+            ocr: Self::load_ocr_config()?,
+
+            transcription: Self::load_transcription_config()?,
+
+            video: Self::load_video_config()?,
+
+            sistema_ts: Self::load_sistema_ts_config()?,
         };
 
         Ok(config)
     }
 
+    /// Load asymmetric JWT signing keys from environment variables
+    ///
+    /// Reads JWT_ALGORITHM (HS256, RS256 or EdDSA; default HS256), and for the
+    /// asymmetric algorithms JWT_KEYS_DIR and JWT_ACTIVE_KID.
+    fn load_jwt_key_ring() -> anyhow::Result<Option<Arc<JwtKeyRing>>> {
+        let algorithm_name = std::env::var("JWT_ALGORITHM").unwrap_or_else(|_| "HS256".to_string());
+        let algorithm = JwtAlgorithm::from_str(&algorithm_name)
+            .ok_or_else(|| anyhow::anyhow!("Unsupported JWT_ALGORITHM '{}'", algorithm_name))?;
+
+        if !algorithm.is_asymmetric() {
+            return Ok(None);
+        }
+
+        let keys_dir = std::env::var("JWT_KEYS_DIR")
+            .map_err(|_| anyhow::anyhow!("JWT_KEYS_DIR must be set when JWT_ALGORITHM is {}", algorithm.as_str()))?;
+        let active_kid = std::env::var("JWT_ACTIVE_KID")
+            .map_err(|_| anyhow::anyhow!("JWT_ACTIVE_KID must be set when JWT_ALGORITHM is {}", algorithm.as_str()))?;
+
+        let key_ring = JwtKeyRing::load_from_dir(algorithm, std::path::Path::new(&keys_dir), &active_kid)?;
+        tracing::info!("JWT access tokens signed with {} (active key: {})", algorithm.as_str(), active_kid);
+
+        Ok(Some(Arc::new(key_ring)))
+    }
+
+    /// Load CAPTCHA configuration from environment variables
+    ///
+    /// Reads CAPTCHA_PROVIDER (hcaptcha or turnstile), CAPTCHA_SITE_KEY,
+    /// CAPTCHA_SECRET_KEY and CAPTCHA_FAILURE_THRESHOLD. Returns None when no
+    /// provider is set.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the provider is unknown or a key is missing.
+    fn load_captcha_config() -> anyhow::Result<Option<CaptchaConfig>> {
+        let provider = match std::env::var("CAPTCHA_PROVIDER") {
+            Ok(name) if !name.trim().is_empty() => CaptchaProvider::from_name(&name)
+                .ok_or_else(|| anyhow::anyhow!("CAPTCHA_PROVIDER must be hcaptcha or turnstile"))?,
+            _ => return Ok(None),
+        };
+
+        let site_key = std::env::var("CAPTCHA_SITE_KEY")
+            .map_err(|_| anyhow::anyhow!("CAPTCHA_SITE_KEY must be set when CAPTCHA_PROVIDER is set"))?;
+        let secret_key = std::env::var("CAPTCHA_SECRET_KEY")
+            .map_err(|_| anyhow::anyhow!("CAPTCHA_SECRET_KEY must be set when CAPTCHA_PROVIDER is set"))?;
+        let failure_threshold = std::env::var("CAPTCHA_FAILURE_THRESHOLD")
+            .unwrap_or_else(|_| "3".to_string())
+            .parse()
+            .unwrap_or(3);
+
+        Ok(Some(CaptchaConfig {
+            provider,
+            site_key,
+            secret_key,
+            failure_threshold,
+        }))
+    }
+
+    /// Load the admin route IP allowlist from environment variables
+    ///
+    /// Reads ADMIN_IP_ALLOWLIST as a comma-separated list of CIDR ranges or
+    /// addresses. Unset or empty disables the allowlist.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an entry is not a valid address or CIDR range.
+    fn load_admin_ip_allowlist() -> anyhow::Result<Vec<IpNetwork>> {
+        let raw = std::env::var("ADMIN_IP_ALLOWLIST").unwrap_or_default();
+
+        raw.split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                entry.parse::<IpNetwork>().map_err(|_| {
+                    anyhow::anyhow!("ADMIN_IP_ALLOWLIST contains an invalid entry: {}", entry)
+                })
+            })
+            .collect()
+    }
+
+    /// Load the trusted reverse proxies from environment variables
+    ///
+    /// Reads TRUSTED_PROXIES as a comma-separated list of CIDR ranges or
+    /// addresses. Unset or empty trusts no proxy: the connection peer is the
+    /// client IP.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an entry is not a valid address or CIDR range.
+    fn load_trusted_proxies() -> anyhow::Result<Vec<IpNetwork>> {
+        let raw = std::env::var("TRUSTED_PROXIES").unwrap_or_default();
+
+        raw.split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                entry.parse::<IpNetwork>().map_err(|_| {
+                    anyhow::anyhow!("TRUSTED_PROXIES contains an invalid entry: {}", entry)
+                })
+            })
+            .collect()
+    }
+
+    /// Load SIEM streaming configuration from environment variables
+    ///
+    /// Reads SIEM_ENDPOINT (udp://, tcp:// or http(s):// URL), SIEM_FORMAT
+    /// (cef or json; default cef for syslog, json for HTTP), SIEM_AUTH_TOKEN,
+    /// SIEM_BUFFER_SIZE and SIEM_HOSTNAME. Returns None when no endpoint is set.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the endpoint scheme or the format is unknown.
+    pub fn load_siem_config() -> anyhow::Result<Option<SiemConfig>> {
+        let transport = match std::env::var("SIEM_ENDPOINT") {
+            Ok(endpoint) if !endpoint.trim().is_empty() => SiemTransport::from_endpoint(&endpoint)
+                .ok_or_else(|| anyhow::anyhow!("SIEM_ENDPOINT must start with udp://, tcp://, http:// or https://"))?,
+            _ => return Ok(None),
+        };
+
+        let format = match std::env::var("SIEM_FORMAT") {
+            Ok(name) if !name.trim().is_empty() => SiemFormat::from_name(&name)
+                .ok_or_else(|| anyhow::anyhow!("SIEM_FORMAT must be cef or json"))?,
+            _ => match transport {
+                SiemTransport::Http(_) => SiemFormat::Json,
+                _ => SiemFormat::Cef,
+            },
+        };
+
+        let auth_token = std::env::var("SIEM_AUTH_TOKEN")
+            .ok()
+            .filter(|token| !token.trim().is_empty());
+        let buffer_size = std::env::var("SIEM_BUFFER_SIZE")
+            .unwrap_or_else(|_| "10000".to_string())
+            .parse::<usize>()
+            .unwrap_or(10000)
+            .max(1);
+        let hostname = std::env::var("SIEM_HOSTNAME")
+            .or_else(|_| std::env::var("HOSTNAME"))
+            .unwrap_or_else(|_| "docpat".to_string());
+
+        Ok(Some(SiemConfig {
+            transport,
+            format,
+            auth_token,
+            buffer_size,
+            hostname,
+        }))
+    }
+
+    /// Load OCR configuration from environment variables
+    ///
+    /// Reads OCR_PROVIDER (tesseract or http), OCR_TESSERACT_PATH,
+    /// OCR_LANGUAGES, OCR_ENDPOINT (required for http), OCR_AUTH_TOKEN and
+    /// OCR_TIMEOUT_SECS. Returns None when no provider is set.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the provider is unknown or http has no endpoint.
+    fn load_ocr_config() -> anyhow::Result<Option<OcrConfig>> {
+        let provider = match std::env::var("OCR_PROVIDER") {
+            Ok(name) if !name.trim().is_empty() => match name.trim().to_lowercase().as_str() {
+                "tesseract" => OcrProvider::Tesseract,
+                "http" => {
+                    let endpoint = std::env::var("OCR_ENDPOINT")
+                        .ok()
+                        .map(|url| url.trim().to_string())
+                        .filter(|url| url.starts_with("https://") || url.starts_with("http://"))
+                        .ok_or_else(|| {
+                            anyhow::anyhow!(
+                                "OCR_ENDPOINT must be an http(s):// URL when OCR_PROVIDER is http"
+                            )
+                        })?;
+                    OcrProvider::Http(endpoint)
+                }
+                _ => anyhow::bail!("OCR_PROVIDER must be tesseract or http"),
+            },
+            _ => return Ok(None),
+        };
+
+        let tesseract_path = std::env::var("OCR_TESSERACT_PATH")
+            .ok()
+            .filter(|path| !path.trim().is_empty())
+            .unwrap_or_else(|| "tesseract".to_string());
+        let languages = std::env::var("OCR_LANGUAGES")
+            .ok()
+            .filter(|langs| !langs.trim().is_empty())
+            .unwrap_or_else(|| "ita+eng".to_string());
+        let auth_token = std::env::var("OCR_AUTH_TOKEN")
+            .ok()
+            .filter(|token| !token.trim().is_empty());
+        let timeout_secs = std::env::var("OCR_TIMEOUT_SECS")
+            .unwrap_or_else(|_| "120".to_string())
+            .parse::<u64>()
+            .unwrap_or(120)
+            .max(1);
+
+        Ok(Some(OcrConfig {
+            provider,
+            tesseract_path,
+            languages,
+            auth_token,
+            timeout: Duration::from_secs(timeout_secs),
+        }))
+    }
+
+    /// Load transcription configuration from environment variables
+    ///
+    /// Reads TRANSCRIPTION_PROVIDER (`whisper` or `http`; unset disables
+    /// transcription), TRANSCRIPTION_WHISPER_MODEL (required for whisper),
+    /// TRANSCRIPTION_WHISPER_PATH, TRANSCRIPTION_ENDPOINT (required for
+    /// http), TRANSCRIPTION_AUTH_TOKEN, TRANSCRIPTION_LANGUAGE and
+    /// TRANSCRIPTION_TIMEOUT_SECS.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the provider is unknown, whisper has no model or
+    /// http has no endpoint.
+    fn load_transcription_config() -> anyhow::Result<Option<TranscriptionConfig>> {
+        let provider = match std::env::var("TRANSCRIPTION_PROVIDER") {
+            Ok(name) if !name.trim().is_empty() => match name.trim().to_lowercase().as_str() {
+                "whisper" => {
+                    let model = std::env::var("TRANSCRIPTION_WHISPER_MODEL")
+                        .ok()
+                        .map(|path| path.trim().to_string())
+                        .filter(|path| !path.is_empty())
+                        .ok_or_else(|| {
+                            anyhow::anyhow!(
+                                "TRANSCRIPTION_WHISPER_MODEL must be set when TRANSCRIPTION_PROVIDER is whisper"
+                            )
+                        })?;
+                    TranscriptionProvider::Whisper(model)
+                }
+                "http" => {
+                    let endpoint = std::env::var("TRANSCRIPTION_ENDPOINT")
+                        .ok()
+                        .map(|url| url.trim().to_string())
+                        .filter(|url| url.starts_with("https://") || url.starts_with("http://"))
+                        .ok_or_else(|| {
+                            anyhow::anyhow!(
+                                "TRANSCRIPTION_ENDPOINT must be an http(s):// URL when TRANSCRIPTION_PROVIDER is http"
+                            )
+                        })?;
+                    TranscriptionProvider::Http(endpoint)
+                }
+                _ => anyhow::bail!("TRANSCRIPTION_PROVIDER must be whisper or http"),
+            },
+            _ => return Ok(None),
+        };
+
+        let whisper_path = std::env::var("TRANSCRIPTION_WHISPER_PATH")
+            .ok()
+            .filter(|path| !path.trim().is_empty())
+            .unwrap_or_else(|| "whisper-cli".to_string());
+        let language = std::env::var("TRANSCRIPTION_LANGUAGE")
+            .ok()
+            .filter(|lang| !lang.trim().is_empty())
+            .unwrap_or_else(|| "it".to_string());
+        let auth_token = std::env::var("TRANSCRIPTION_AUTH_TOKEN")
+            .ok()
+            .filter(|token| !token.trim().is_empty());
+        let timeout_secs = std::env::var("TRANSCRIPTION_TIMEOUT_SECS")
+            .unwrap_or_else(|_| "600".to_string())
+            .parse::<u64>()
+            .unwrap_or(600)
+            .max(1);
+
+        Ok(Some(TranscriptionConfig {
+            provider,
+            whisper_path,
+            language,
+            auth_token,
+            timeout: Duration::from_secs(timeout_secs),
+        }))
+    }
+
+    /// Load televisit video room configuration from environment variables
+    ///
+    /// Reads VIDEO_PROVIDER (`jitsi` or `http`; unset disables televisits),
+    /// VIDEO_JITSI_URL (default: https://meet.jit.si), VIDEO_ENDPOINT
+    /// (required for http), VIDEO_AUTH_TOKEN and VIDEO_TIMEOUT_SECS.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the provider is unknown or a URL is not http(s).
+    fn load_video_config() -> anyhow::Result<Option<VideoConfig>> {
+        let is_http_url = |url: &String| url.starts_with("https://") || url.starts_with("http://");
+
+        let provider = match std::env::var("VIDEO_PROVIDER") {
+            Ok(name) if !name.trim().is_empty() => match name.trim().to_lowercase().as_str() {
+                "jitsi" => {
+                    let base_url = std::env::var("VIDEO_JITSI_URL")
+                        .ok()
+                        .map(|url| url.trim().to_string())
+                        .filter(|url| !url.is_empty())
+                        .unwrap_or_else(|| "https://meet.jit.si".to_string());
+                    if !is_http_url(&base_url) {
+                        anyhow::bail!("VIDEO_JITSI_URL must be an http(s):// URL");
+                    }
+                    VideoProvider::Jitsi(base_url)
+                }
+                "http" => {
+                    let endpoint = std::env::var("VIDEO_ENDPOINT")
+                        .ok()
+                        .map(|url| url.trim().to_string())
+                        .filter(is_http_url)
+                        .ok_or_else(|| {
+                            anyhow::anyhow!(
+                                "VIDEO_ENDPOINT must be an http(s):// URL when VIDEO_PROVIDER is http"
+                            )
+                        })?;
+                    VideoProvider::Http(endpoint)
+                }
+                _ => anyhow::bail!("VIDEO_PROVIDER must be jitsi or http"),
+            },
+            _ => return Ok(None),
+        };
+
+        let auth_token = std::env::var("VIDEO_AUTH_TOKEN")
+            .ok()
+            .filter(|token| !token.trim().is_empty());
+        let timeout_secs = std::env::var("VIDEO_TIMEOUT_SECS")
+            .unwrap_or_else(|_| "15".to_string())
+            .parse::<u64>()
+            .unwrap_or(15)
+            .max(1);
+
+        Ok(Some(VideoConfig {
+            provider,
+            auth_token,
+            timeout: Duration::from_secs(timeout_secs),
+        }))
+    }
+
+    /// Load Sistema TS dematerialized prescription configuration
+    ///
+    /// Reads SISTEMA_TS_ENDPOINT, SISTEMA_TS_USERNAME, SISTEMA_TS_PASSWORD,
+    /// SISTEMA_TS_PINCODE, SISTEMA_TS_PRESCRIBER_CF, SISTEMA_TS_REGION_CODE,
+    /// SISTEMA_TS_ASL_CODE, SISTEMA_TS_SPECIALIZATION,
+    /// SISTEMA_TS_PUBLIC_KEY_PATH, SISTEMA_TS_CALLBACK_SECRET and
+    /// SISTEMA_TS_TIMEOUT_SECS. Returns None when no endpoint is set.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the endpoint is set but a credential or the public
+    /// key is missing.
+    fn load_sistema_ts_config() -> anyhow::Result<Option<SistemaTsConfig>> {
+        let endpoint = match std::env::var("SISTEMA_TS_ENDPOINT") {
+            Ok(url) if !url.trim().is_empty() => url.trim().trim_end_matches('/').to_string(),
+            _ => return Ok(None),
+        };
+        if !endpoint.starts_with("https://") {
+            anyhow::bail!("SISTEMA_TS_ENDPOINT must be an https:// URL");
+        }
+
+        let required = |name: &str| -> anyhow::Result<String> {
+            std::env::var(name)
+                .ok()
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+                .ok_or_else(|| anyhow::anyhow!("{} must be set when SISTEMA_TS_ENDPOINT is set", name))
+        };
+
+        let public_key_path = required("SISTEMA_TS_PUBLIC_KEY_PATH")?;
+        let public_key_pem = std::fs::read_to_string(&public_key_path).map_err(|e| {
+            anyhow::anyhow!("Failed to read SISTEMA_TS_PUBLIC_KEY_PATH {}: {}", public_key_path, e)
+        })?;
+
+        let timeout_secs = std::env::var("SISTEMA_TS_TIMEOUT_SECS")
+            .unwrap_or_else(|_| "30".to_string())
+            .parse::<u64>()
+            .unwrap_or(30)
+            .max(1);
+
+        Ok(Some(SistemaTsConfig {
+            endpoint,
+            username: required("SISTEMA_TS_USERNAME")?,
+            password: required("SISTEMA_TS_PASSWORD")?,
+            pincode: required("SISTEMA_TS_PINCODE")?,
+            prescriber_fiscal_code: required("SISTEMA_TS_PRESCRIBER_CF")?.to_uppercase(),
+            region_code: required("SISTEMA_TS_REGION_CODE")?,
+            asl_code: required("SISTEMA_TS_ASL_CODE")?,
+            specialization: std::env::var("SISTEMA_TS_SPECIALIZATION")
+                .ok()
+                .filter(|value| !value.trim().is_empty())
+                .unwrap_or_else(|| "P".to_string()),
+            public_key_pem,
+            callback_secret: std::env::var("SISTEMA_TS_CALLBACK_SECRET")
+                .ok()
+                .filter(|secret| !secret.trim().is_empty()),
+            timeout: Duration::from_secs(timeout_secs),
+        }))
+    }
+
     /// Load TLS configuration from environment variables
     ///
     /// Reads TLS_ENABLED, TLS_CERT_PATH, and TLS_KEY_PATH from environment.
@@ -336,4 +1147,80 @@ mod tests {
         assert_eq!(host, "0.0.0.0");
         assert_eq!(port, 8000);
     }
+
+    #[test]
+    fn test_siem_transport_from_endpoint() {
+        assert_eq!(
+            SiemTransport::from_endpoint("udp://siem.local:514"),
+            Some(SiemTransport::Udp("siem.local:514".to_string()))
+        );
+        assert_eq!(
+            SiemTransport::from_endpoint(" tcp://10.0.0.5:6514 "),
+            Some(SiemTransport::Tcp("10.0.0.5:6514".to_string()))
+        );
+        assert_eq!(
+            SiemTransport::from_endpoint("https://collector.example.com/services/collector"),
+            Some(SiemTransport::Http(
+                "https://collector.example.com/services/collector".to_string()
+            ))
+        );
+        assert_eq!(SiemTransport::from_endpoint("siem.local:514"), None);
+    }
+
+    #[test]
+    fn test_ocr_config_debug_redacts_token() {
+        let config = OcrConfig {
+            provider: OcrProvider::Http("https://ocr.example.com/recognize".to_string()),
+            tesseract_path: "tesseract".to_string(),
+            languages: "ita+eng".to_string(),
+            auth_token: Some("ocr-secret-token".to_string()),
+            timeout: Duration::from_secs(120),
+        };
+
+        let debug = format!("{:?}", config);
+        assert!(debug.contains("[REDACTED]"));
+        assert!(!debug.contains("ocr-secret-token"));
+        assert_eq!(config.auth_token(), Some("ocr-secret-token"));
+    }
+
+    #[test]
+    fn test_transcription_config_debug_redacts_token() {
+        let config = TranscriptionConfig {
+            provider: TranscriptionProvider::Http("https://stt.example.com/transcribe".to_string()),
+            whisper_path: "whisper-cli".to_string(),
+            language: "it".to_string(),
+            auth_token: Some("stt-secret-token".to_string()),
+            timeout: Duration::from_secs(600),
+        };
+
+        let debug = format!("{:?}", config);
+        assert!(debug.contains("[REDACTED]"));
+        assert!(!debug.contains("stt-secret-token"));
+        assert_eq!(config.auth_token(), Some("stt-secret-token"));
+    }
+
+    #[test]
+    fn test_video_config_debug_redacts_token() {
+        let config = VideoConfig {
+            provider: VideoProvider::Http("https://video.example.com/rooms".to_string()),
+            auth_token: Some("video-secret-token".to_string()),
+            timeout: Duration::from_secs(15),
+        };
+
+        let debug = format!("{:?}", config);
+        assert!(debug.contains("[REDACTED]"));
+        assert!(!debug.contains("video-secret-token"));
+        assert_eq!(config.auth_token(), Some("video-secret-token"));
+    }
+
+    #[test]
+    fn test_sistema_ts_config_debug_redacts_credentials() {
+        let config = SistemaTsConfig::test_config();
+
+        let debug = format!("{:?}", config);
+        assert!(!debug.contains("sistema-ts-password"));
+        assert!(!debug.contains("1234567890"));
+        assert!(!debug.contains("callback-secret"));
+        assert!(debug.contains("BNCGVN70A01F205X"));
+    }
 }
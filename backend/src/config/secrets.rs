@@ -0,0 +1,412 @@
+/*!
+ * Secret Providers
+ *
+ * Key material (ENCRYPTION_KEY, JWT_SECRET, JWT_REFRESH_SECRET) comes from
+ * the `SecretProvider` selected with SECRET_PROVIDER:
+ * - `env` (default): environment variables of the same name
+ * - `vault`: a HashiCorp Vault KV v2 secret (VAULT_ADDR, VAULT_TOKEN,
+ *   VAULT_SECRET_PATH, optional VAULT_NAMESPACE) with one field per name
+ * - `aws-kms`: `<NAME>_KMS` environment variables holding base64 ciphertext
+ *   blobs, decrypted with AWS KMS (`kms` feature; credentials from the usual
+ *   AWS chain, optional AWS_KMS_KEY_ID). Blobs are encrypted with the
+ *   encryption context `secret=<NAME>`.
+ * - `age`: an age-encrypted file (AGE_SECRETS_FILE) of `NAME=value` lines,
+ *   decrypted with the X25519 identity in AGE_IDENTITY_FILE
+ *
+ * Secrets are fetched once at startup; the other settings still come from
+ * the environment.
+ */
+
+use anyhow::{Context, Result};
+use futures::future::BoxFuture;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Environment variable of the encryption key
+pub const ENCRYPTION_KEY: &str = "ENCRYPTION_KEY";
+
+/// Environment variable of the access token secret
+pub const JWT_SECRET: &str = "JWT_SECRET";
+
+/// Environment variable of the refresh token secret
+pub const JWT_REFRESH_SECRET: &str = "JWT_REFRESH_SECRET";
+
+/// Timeout of requests to Vault
+const VAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Source of key material
+pub trait SecretProvider: Send + Sync {
+    /// Provider name, for logs
+    fn name(&self) -> &'static str;
+
+    /// Value of a secret; none when the provider does not have it
+    fn fetch<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<Option<String>>>;
+}
+
+/// Secret values of the configuration
+#[derive(Clone)]
+pub struct ConfigSecrets {
+    pub encryption_key: String,
+    pub jwt_secret: String,
+    pub jwt_refresh_secret: String,
+}
+
+impl ConfigSecrets {
+    /// Fetch every secret from a provider
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the provider fails or lacks one of the secrets.
+    pub async fn fetch(provider: &dyn SecretProvider) -> Result<Self> {
+        let require = |name: &'static str, value: Option<String>| {
+            value.ok_or_else(|| anyhow::anyhow!("{} not found in {} secrets", name, provider.name()))
+        };
+
+        Ok(Self {
+            encryption_key: require(ENCRYPTION_KEY, provider.fetch(ENCRYPTION_KEY).await?)?,
+            jwt_secret: require(JWT_SECRET, provider.fetch(JWT_SECRET).await?)?,
+            jwt_refresh_secret: require(
+                JWT_REFRESH_SECRET,
+                provider.fetch(JWT_REFRESH_SECRET).await?,
+            )?,
+        })
+    }
+
+    /// Secrets from environment variables
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if one of them is not set.
+    pub fn from_env() -> Result<Self> {
+        let require = |name: &str| {
+            std::env::var(name).map_err(|_| anyhow::anyhow!("{} must be set", name))
+        };
+
+        Ok(Self {
+            encryption_key: require(ENCRYPTION_KEY)?,
+            jwt_secret: require(JWT_SECRET)?,
+            jwt_refresh_secret: require(JWT_REFRESH_SECRET)?,
+        })
+    }
+}
+
+// Custom Debug implementation to prevent secret leakage in logs
+impl std::fmt::Debug for ConfigSecrets {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConfigSecrets")
+            .field("encryption_key", &"[REDACTED]")
+            .field("jwt_secret", &"[REDACTED]")
+            .field("jwt_refresh_secret", &"[REDACTED]")
+            .finish()
+    }
+}
+
+/// Build the provider selected with SECRET_PROVIDER
+///
+/// # Errors
+///
+/// Returns an error if the provider is unknown or its settings are missing.
+pub fn secret_provider_from_env() -> Result<Box<dyn SecretProvider>> {
+    let name = std::env::var("SECRET_PROVIDER").unwrap_or_default();
+
+    Ok(match name.trim().to_lowercase().as_str() {
+        "" | "env" => Box::new(EnvSecretProvider),
+        "vault" => Box::new(VaultSecretProvider::from_env()?),
+        "aws-kms" => aws_kms_provider()?,
+        "age" => Box::new(AgeSecretProvider::from_env()?),
+        other => anyhow::bail!(
+            "SECRET_PROVIDER must be env, vault, aws-kms or age (got '{}')",
+            other
+        ),
+    })
+}
+
+#[cfg(feature = "kms")]
+fn aws_kms_provider() -> Result<Box<dyn SecretProvider>> {
+    Ok(Box::new(AwsKmsSecretProvider::from_env()))
+}
+
+#[cfg(not(feature = "kms"))]
+fn aws_kms_provider() -> Result<Box<dyn SecretProvider>> {
+    anyhow::bail!("SECRET_PROVIDER=aws-kms requires building with the kms feature")
+}
+
+/// Environment variables
+pub struct EnvSecretProvider;
+
+impl SecretProvider for EnvSecretProvider {
+    fn name(&self) -> &'static str {
+        "env"
+    }
+
+    fn fetch<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<Option<String>>> {
+        Box::pin(async move { Ok(std::env::var(name).ok()) })
+    }
+}
+
+/// HashiCorp Vault KV v2 secret
+pub struct VaultSecretProvider {
+    client: reqwest::Client,
+    /// URL of the secret, e.g. https://vault:8200/v1/secret/data/docpat
+    url: String,
+    token: String,
+    namespace: Option<String>,
+}
+
+impl VaultSecretProvider {
+    /// Provider configured with VAULT_ADDR, VAULT_TOKEN, VAULT_SECRET_PATH
+    /// (e.g. `secret/data/docpat`) and VAULT_NAMESPACE
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a setting is missing.
+    pub fn from_env() -> Result<Self> {
+        let addr = std::env::var("VAULT_ADDR")
+            .map_err(|_| anyhow::anyhow!("VAULT_ADDR must be set when SECRET_PROVIDER is vault"))?;
+        let token = std::env::var("VAULT_TOKEN")
+            .map_err(|_| anyhow::anyhow!("VAULT_TOKEN must be set when SECRET_PROVIDER is vault"))?;
+        let path = std::env::var("VAULT_SECRET_PATH").map_err(|_| {
+            anyhow::anyhow!("VAULT_SECRET_PATH must be set when SECRET_PROVIDER is vault")
+        })?;
+        let namespace = std::env::var("VAULT_NAMESPACE")
+            .ok()
+            .filter(|namespace| !namespace.trim().is_empty());
+
+        Ok(Self {
+            client: reqwest::Client::builder()
+                .timeout(VAULT_TIMEOUT)
+                .build()
+                .context("Failed to build Vault HTTP client")?,
+            url: format!(
+                "{}/v1/{}",
+                addr.trim_end_matches('/'),
+                path.trim_start_matches('/')
+            ),
+            token,
+            namespace,
+        })
+    }
+}
+
+impl SecretProvider for VaultSecretProvider {
+    fn name(&self) -> &'static str {
+        "vault"
+    }
+
+    fn fetch<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<Option<String>>> {
+        Box::pin(async move {
+            let mut request = self.client.get(&self.url).header("X-Vault-Token", &self.token);
+            if let Some(namespace) = &self.namespace {
+                request = request.header("X-Vault-Namespace", namespace);
+            }
+
+            let body: serde_json::Value = request
+                .send()
+                .await
+                .context("Vault unreachable")?
+                .error_for_status()
+                .context("Vault refused to read the secret")?
+                .json()
+                .await
+                .context("Invalid Vault response")?;
+
+            Ok(body["data"]["data"][name].as_str().map(str::to_string))
+        })
+    }
+}
+
+/// Ciphertext blobs in `<NAME>_KMS` environment variables, decrypted with
+/// AWS KMS
+#[cfg(feature = "kms")]
+pub struct AwsKmsSecretProvider {
+    key_id: Option<String>,
+}
+
+#[cfg(feature = "kms")]
+impl AwsKmsSecretProvider {
+    /// Provider using AWS_KMS_KEY_ID when set (required for asymmetric keys)
+    pub fn from_env() -> Self {
+        Self {
+            key_id: std::env::var("AWS_KMS_KEY_ID")
+                .ok()
+                .filter(|key_id| !key_id.trim().is_empty()),
+        }
+    }
+}
+
+#[cfg(feature = "kms")]
+impl SecretProvider for AwsKmsSecretProvider {
+    fn name(&self) -> &'static str {
+        "aws-kms"
+    }
+
+    fn fetch<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<Option<String>>> {
+        use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+
+        Box::pin(async move {
+            let Ok(blob) = std::env::var(format!("{}_KMS", name)) else {
+                return Ok(None);
+            };
+            let ciphertext = BASE64
+                .decode(blob.trim())
+                .with_context(|| format!("{}_KMS is not base64", name))?;
+
+            let aws_config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+            let client = aws_sdk_kms::Client::new(&aws_config);
+
+            let mut request = client
+                .decrypt()
+                .ciphertext_blob(aws_sdk_kms::primitives::Blob::new(ciphertext))
+                .encryption_context("secret", name);
+            if let Some(key_id) = &self.key_id {
+                request = request.key_id(key_id);
+            }
+
+            let output = request
+                .send()
+                .await
+                .with_context(|| format!("AWS KMS failed to decrypt {}", name))?;
+            let plaintext = output
+                .plaintext()
+                .with_context(|| format!("AWS KMS returned no plaintext for {}", name))?;
+
+            String::from_utf8(plaintext.as_ref().to_vec())
+                .map(Some)
+                .with_context(|| format!("{} is not valid UTF-8", name))
+        })
+    }
+}
+
+/// age-encrypted file of `NAME=value` lines
+pub struct AgeSecretProvider {
+    secrets: HashMap<String, String>,
+}
+
+impl AgeSecretProvider {
+    /// Provider decrypting AGE_SECRETS_FILE with the identity in
+    /// AGE_IDENTITY_FILE (as written by `age-keygen`)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a file cannot be read or decrypted.
+    pub fn from_env() -> Result<Self> {
+        let secrets_file = std::env::var("AGE_SECRETS_FILE")
+            .map_err(|_| anyhow::anyhow!("AGE_SECRETS_FILE must be set when SECRET_PROVIDER is age"))?;
+        let identity_file = std::env::var("AGE_IDENTITY_FILE")
+            .map_err(|_| anyhow::anyhow!("AGE_IDENTITY_FILE must be set when SECRET_PROVIDER is age"))?;
+
+        let identity = std::fs::read_to_string(&identity_file)
+            .with_context(|| format!("Failed to read {}", identity_file))?;
+        let encrypted = std::fs::read(&secrets_file)
+            .with_context(|| format!("Failed to read {}", secrets_file))?;
+
+        let plaintext = decrypt_age(&encrypted, &identity)
+            .with_context(|| format!("Failed to decrypt {}", secrets_file))?;
+
+        Ok(Self {
+            secrets: parse_secret_lines(&plaintext),
+        })
+    }
+}
+
+impl SecretProvider for AgeSecretProvider {
+    fn name(&self) -> &'static str {
+        "age"
+    }
+
+    fn fetch<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<Option<String>>> {
+        Box::pin(async move { Ok(self.secrets.get(name).cloned()) })
+    }
+}
+
+/// Decrypt an age file (binary or armored) with an X25519 identity file
+fn decrypt_age(encrypted: &[u8], identity_file: &str) -> Result<String> {
+    use std::io::Read;
+
+    let identity: age::x25519::Identity = identity_file
+        .lines()
+        .map(str::trim)
+        .find(|line| line.starts_with("AGE-SECRET-KEY-"))
+        .context("No AGE-SECRET-KEY in the identity file")?
+        .parse()
+        .map_err(|e| anyhow::anyhow!("Invalid age identity: {}", e))?;
+
+    let decryptor = age::Decryptor::new(age::armor::ArmoredReader::new(encrypted))
+        .context("Not an age file")?;
+    if decryptor.is_scrypt() {
+        anyhow::bail!("Passphrase-encrypted age files are not supported");
+    }
+
+    let mut reader = decryptor
+        .decrypt(std::iter::once(&identity as &dyn age::Identity))
+        .context("The identity cannot decrypt the file")?;
+    let mut plaintext = String::new();
+    reader
+        .read_to_string(&mut plaintext)
+        .context("Decrypted secrets are not valid UTF-8")?;
+
+    Ok(plaintext)
+}
+
+/// `NAME=value` lines (dotenv style: `#` comments, optional quotes)
+fn parse_secret_lines(text: &str) -> HashMap<String, String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(name, value)| {
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+                .unwrap_or(value);
+            (
+                name.trim().trim_start_matches("export ").trim().to_string(),
+                value.to_string(),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use age::secrecy::ExposeSecret;
+    use std::io::Write;
+
+    #[test]
+    fn test_parse_secret_lines() {
+        let secrets = parse_secret_lines(
+            "# docpat\nENCRYPTION_KEY=abc==\nexport JWT_SECRET=\"s3cr=t\"\n\nJWT_REFRESH_SECRET='r'\n",
+        );
+        assert_eq!(secrets.get("ENCRYPTION_KEY").map(String::as_str), Some("abc=="));
+        assert_eq!(secrets.get("JWT_SECRET").map(String::as_str), Some("s3cr=t"));
+        assert_eq!(secrets.get("JWT_REFRESH_SECRET").map(String::as_str), Some("r"));
+        assert_eq!(secrets.len(), 3);
+    }
+
+    #[test]
+    fn test_decrypt_age() {
+        let identity = age::x25519::Identity::generate();
+        let recipient = identity.to_public();
+        let encryptor =
+            age::Encryptor::with_recipients(std::iter::once(&recipient as &dyn age::Recipient))
+                .expect("one recipient");
+        let mut encrypted = Vec::new();
+        let mut writer = encryptor.wrap_output(&mut encrypted).unwrap();
+        writer.write_all(b"ENCRYPTION_KEY=abc==\n").unwrap();
+        writer.finish().unwrap();
+
+        let identity_file = format!(
+            "# created: 2026-04-20\n# public key: {}\n{}\n",
+            identity.to_public(),
+            identity.to_string().expose_secret()
+        );
+        let plaintext = decrypt_age(&encrypted, &identity_file).unwrap();
+        assert_eq!(plaintext, "ENCRYPTION_KEY=abc==\n");
+
+        let other = age::x25519::Identity::generate();
+        assert!(decrypt_age(&encrypted, other.to_string().expose_secret()).is_err());
+    }
+}
@@ -2,9 +2,10 @@
  * Database Module
  *
  * Handles PostgreSQL database connection pooling and provides
- * database access utilities.
+ * database access utilities, including the row-level security context.
  */
 
 pub mod pool;
+pub mod rls;
 
 pub use pool::create_pool;
@@ -86,38 +86,6 @@ async fn test_connection(pool: &PgPool) -> Result<(), SqlxError> {
     Ok(())
 }
 
-/// Set PostgreSQL row-level security session variables
-///
-/// These variables are required for row-level security policies to work correctly.
-/// They should be set at the beginning of each transaction or request.
-///
-/// # Arguments
-///
-/// * `pool` - Database connection pool
-/// * `user_id` - UUID of the current authenticated user
-/// * `user_role` - Role of the current user (ADMIN or DOCTOR)
-///
-/// # Errors
-///
-/// Returns an error if setting the session variables fails
-pub async fn set_rls_context(
-    pool: &PgPool,
-    user_id: &uuid::Uuid,
-    user_role: &str,
-) -> Result<(), SqlxError> {
-    sqlx::query("SET LOCAL app.current_user_id = $1")
-        .bind(user_id.to_string())
-        .execute(pool)
-        .await?;
-
-    sqlx::query("SET LOCAL app.current_user_role = $1")
-        .bind(user_role)
-        .execute(pool)
-        .await?;
-
-    Ok(())
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -159,7 +127,8 @@ mod tests {
         let config = test_db_config();
         let pool = create_pool(&config).await.unwrap();
         let user_id = uuid::Uuid::new_v4();
-        let result = set_rls_context(&pool, &user_id, "DOCTOR").await;
+        let mut tx = pool.begin().await.unwrap();
+        let result = crate::db::rls::apply_rls_context(&mut tx, user_id, "DOCTOR").await;
         assert!(result.is_ok());
     }
 }
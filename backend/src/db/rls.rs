@@ -0,0 +1,487 @@
+/*!
+ * Row-Level Security Context
+ *
+ * RLS policies read `app.current_user_id` and `app.current_user_role`, which
+ * must be set (transaction-local) on every connection before querying
+ * protected tables.
+ *
+ * `jwt_auth_middleware` runs each request in an RLS scope for the
+ * authenticated user. Services begin their work with `begin_with_rls`; for
+ * that user it hands out the request's shared transaction, begun on first use
+ * with the context set once (the role read from `users`, so a role change
+ * applies from the next request rather than when the access token expires).
+ * Each service operation runs in a savepoint of it: committing the operation
+ * releases the savepoint, dropping it uncommitted rolls the operation back.
+ * The middleware commits the request transaction when the response is not a
+ * server error and rolls it back otherwise.
+ *
+ * Outside a request (background jobs), for another user, or while the shared
+ * transaction is already held by an operation of the same request (one
+ * service calling another), `begin_with_rls` opens a transaction of its
+ * own. Background jobs act as the dedicated system user (`SYSTEM_USER_ID`).
+ *
+ * Patient portal requests have no user: they act as role 'PATIENT' with
+ * `app.portal_patient_id` set instead. Public online booking acts as role
+ * 'BOOKING', which may only add patients and PENDING appointments.
+ */
+
+use sqlx::{PgConnection, PgPool, Postgres, Transaction};
+use std::future::Future;
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+use tokio::sync::{Mutex, OnceCell, OwnedMutexGuard};
+use uuid::Uuid;
+
+use crate::models::request_context::current_request_id;
+
+/// Dedicated user background jobs act as (created inactive by the
+/// `create_system_user` migration, so it cannot sign in)
+pub const SYSTEM_USER_ID: Uuid = Uuid::from_u128(0x00000000_0000_0000_0000_000000000001);
+
+/// Role of the system user
+pub const SYSTEM_ROLE: &str = "ADMIN";
+
+/// Savepoint wrapping each operation on the request transaction
+const OPERATION_SAVEPOINT: &str = "rls_operation";
+
+/// Transaction-local settings scoped to a single operation (recycle bin,
+/// erasure, merge), cleared when it commits so they do not carry over to the
+/// next operation of the request
+const OPERATION_SETTINGS: &[&str] = &[
+    "app.include_trash",
+    "app.trash_record_id",
+    "app.erasure_patient_id",
+    "app.merge_patient_id",
+    "app.merge_into_patient_id",
+];
+
+tokio::task_local! {
+    /// Authenticated user of the request currently executing
+    static CURRENT_RLS_USER: Arc<RlsUser>;
+}
+
+/// Authenticated user of a request, its role once read from `users`, and the
+/// transaction shared by its services
+#[derive(Debug)]
+struct RlsUser {
+    user_id: Uuid,
+    role: OnceCell<String>,
+    transaction: Arc<Mutex<RequestTransaction>>,
+}
+
+impl RlsUser {
+    fn new(user_id: Uuid) -> Self {
+        Self {
+            user_id,
+            role: OnceCell::new(),
+            transaction: Arc::default(),
+        }
+    }
+
+    /// Role of the user, read from `users` on the first call
+    async fn role(&self, conn: &mut PgConnection) -> Result<String, sqlx::Error> {
+        let user_id = self.user_id;
+        let role = self
+            .role
+            .get_or_try_init(move || lookup_role(conn, user_id))
+            .await?;
+        Ok(role.clone())
+    }
+}
+
+/// Transaction shared by the services of a request
+#[derive(Debug, Default)]
+struct RequestTransaction {
+    tx: Option<Transaction<'static, Postgres>>,
+    /// The last operation ended without committing; its savepoint is rolled
+    /// back before anything else runs on the transaction
+    rollback_pending: bool,
+}
+
+impl RequestTransaction {
+    /// Start an operation: begin the transaction on first use, discard an
+    /// abandoned operation and open its savepoint
+    async fn begin_operation(&mut self, pool: &PgPool, user: &RlsUser) -> Result<(), sqlx::Error> {
+        let tx = match &mut self.tx {
+            Some(tx) => tx,
+            None => {
+                let mut tx = pool.begin().await?;
+                let role = user.role(&mut tx).await?;
+                apply_rls_context(&mut tx, user.user_id, &role).await?;
+                self.tx.insert(tx)
+            }
+        };
+
+        if self.rollback_pending {
+            rollback_operation(tx).await?;
+        }
+        sqlx::query(&format!("SAVEPOINT {}", OPERATION_SAVEPOINT))
+            .execute(&mut **tx)
+            .await?;
+        self.rollback_pending = true;
+
+        Ok(())
+    }
+
+    /// Keep the current operation's changes
+    async fn commit_operation(&mut self) -> Result<(), sqlx::Error> {
+        let tx = self.open()?;
+        sqlx::query(&format!("RELEASE SAVEPOINT {}", OPERATION_SAVEPOINT))
+            .execute(&mut **tx)
+            .await?;
+        for setting in OPERATION_SETTINGS {
+            sqlx::query("SELECT set_config($1, '', true)")
+                .bind(setting)
+                .execute(&mut **tx)
+                .await?;
+        }
+        self.rollback_pending = false;
+        Ok(())
+    }
+
+    /// Discard the current operation's changes
+    async fn rollback_operation(&mut self) -> Result<(), sqlx::Error> {
+        rollback_operation(self.open()?).await?;
+        self.rollback_pending = false;
+        Ok(())
+    }
+
+    fn open(&mut self) -> Result<&mut Transaction<'static, Postgres>, sqlx::Error> {
+        self.tx.as_mut().ok_or(sqlx::Error::PoolClosed)
+    }
+
+    /// Commit (or roll back) the request's work, if it did any
+    async fn finish(self, commit: bool) -> Result<(), sqlx::Error> {
+        let Some(mut tx) = self.tx else {
+            return Ok(());
+        };
+        if !commit {
+            return tx.rollback().await;
+        }
+        if self.rollback_pending {
+            rollback_operation(&mut tx).await?;
+        }
+        tx.commit().await
+    }
+}
+
+/// Roll back to and release the operation savepoint
+async fn rollback_operation(tx: &mut Transaction<'static, Postgres>) -> Result<(), sqlx::Error> {
+    sqlx::query(&format!("ROLLBACK TO SAVEPOINT {}", OPERATION_SAVEPOINT))
+        .execute(&mut **tx)
+        .await?;
+    sqlx::query(&format!("RELEASE SAVEPOINT {}", OPERATION_SAVEPOINT))
+        .execute(&mut **tx)
+        .await?;
+    Ok(())
+}
+
+/// Transaction with the RLS context of a user set, from `begin_with_rls`
+///
+/// Dereferences to the connection, like `sqlx::Transaction`. Within a request
+/// it is an operation on the request's shared transaction; otherwise it is a
+/// transaction of its own. Either way, changes are kept by `commit` and
+/// discarded when it is dropped without committing.
+#[derive(Debug)]
+pub struct RlsTransaction {
+    inner: RlsTransactionInner,
+}
+
+#[derive(Debug)]
+enum RlsTransactionInner {
+    Own(Transaction<'static, Postgres>),
+    Shared(OwnedMutexGuard<RequestTransaction>),
+}
+
+impl RlsTransaction {
+    /// Keep the changes made in this transaction
+    ///
+    /// Within a request they become visible to other connections when the
+    /// request transaction commits.
+    pub async fn commit(self) -> Result<(), sqlx::Error> {
+        match self.inner {
+            RlsTransactionInner::Own(tx) => tx.commit().await,
+            RlsTransactionInner::Shared(mut request) => request.commit_operation().await,
+        }
+    }
+
+    /// Discard the changes made in this transaction
+    pub async fn rollback(self) -> Result<(), sqlx::Error> {
+        match self.inner {
+            RlsTransactionInner::Own(tx) => tx.rollback().await,
+            RlsTransactionInner::Shared(mut request) => request.rollback_operation().await,
+        }
+    }
+}
+
+impl Deref for RlsTransaction {
+    type Target = PgConnection;
+
+    fn deref(&self) -> &PgConnection {
+        match &self.inner {
+            RlsTransactionInner::Own(tx) => tx,
+            RlsTransactionInner::Shared(request) => request
+                .tx
+                .as_deref()
+                .expect("request transaction is begun before it is shared"),
+        }
+    }
+}
+
+impl DerefMut for RlsTransaction {
+    fn deref_mut(&mut self) -> &mut PgConnection {
+        match &mut self.inner {
+            RlsTransactionInner::Own(tx) => tx,
+            RlsTransactionInner::Shared(request) => request
+                .tx
+                .as_deref_mut()
+                .expect("request transaction is begun before it is shared"),
+        }
+    }
+}
+
+/// Authenticated user of the current request, if running inside one
+pub fn current_rls_user() -> Option<Uuid> {
+    CURRENT_RLS_USER.try_with(|user| user.user_id).ok()
+}
+
+/// Run a request with `user_id` as the authenticated user in scope
+///
+/// Once the request completes its shared transaction is committed when
+/// `succeeded` holds for the output, and rolled back otherwise.
+pub async fn with_rls_scope<F, S>(
+    user_id: Uuid,
+    future: F,
+    succeeded: S,
+) -> Result<F::Output, sqlx::Error>
+where
+    F: Future,
+    S: FnOnce(&F::Output) -> bool,
+{
+    let user = Arc::new(RlsUser::new(user_id));
+    let output = CURRENT_RLS_USER.scope(Arc::clone(&user), future).await;
+
+    let request = std::mem::take(&mut *user.transaction.lock().await);
+    request.finish(succeeded(&output)).await?;
+
+    Ok(output)
+}
+
+/// Request scope of `user_id`, when it is the authenticated user
+fn scoped_user(user_id: Uuid) -> Option<Arc<RlsUser>> {
+    CURRENT_RLS_USER
+        .try_with(Arc::clone)
+        .ok()
+        .filter(|user| user.user_id == user_id)
+}
+
+/// Current role of `user_id`, from `users`
+async fn lookup_role(conn: &mut PgConnection, user_id: Uuid) -> Result<String, sqlx::Error> {
+    sqlx::query_scalar("SELECT role::TEXT FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_one(conn)
+        .await
+}
+
+/// Set the RLS context for an explicit user and role
+///
+/// Both settings are transaction-local, so `conn` should be inside a
+/// transaction. The correlation ID of the current request or job goes along
+/// as `app.request_id` (picked up by `notification_queue.correlation_id`).
+pub async fn apply_rls_context(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    role: &str,
+) -> Result<(), sqlx::Error> {
+    let request_id = current_request_id()
+        .map(|id| id.to_string())
+        .unwrap_or_default();
+
+    // set_config() accepts bind parameters (SET LOCAL does not)
+    sqlx::query(
+        "SELECT set_config('app.current_user_id', $1, true), set_config('app.current_user_role', $2, true), set_config('app.request_id', $3, true)",
+    )
+    .bind(user_id.to_string())
+    .bind(role)
+    .bind(request_id)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Set the RLS context for `user_id` on a transaction of its own
+///
+/// The role is read from `users`, once per request when `user_id` is the
+/// authenticated user. Services use `begin_with_rls`, which also shares the
+/// request's transaction.
+pub async fn set_rls_context(conn: &mut PgConnection, user_id: Uuid) -> Result<(), sqlx::Error> {
+    let role = match scoped_user(user_id) {
+        Some(user) => user.role(&mut *conn).await?,
+        None => lookup_role(&mut *conn, user_id).await?,
+    };
+
+    apply_rls_context(conn, user_id, &role).await
+}
+
+/// Make every record in the recycle bin visible for the rest of the
+/// transaction (trash listing and purge)
+///
+/// Records in the recycle bin (`deleted_at` set) of patients, visits and
+/// prescriptions are otherwise hidden by RLS from every query.
+pub async fn include_trashed_records(conn: &mut PgConnection) -> Result<(), sqlx::Error> {
+    sqlx::query("SELECT set_config('app.include_trash', 'on', true)")
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Name the record being moved to or restored from the recycle bin in this
+/// transaction
+///
+/// The record stays visible once its `deleted_at` is set, and admins may
+/// update it even when another provider owns it.
+pub async fn target_trash_record(conn: &mut PgConnection, id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query("SELECT set_config('app.trash_record_id', $1, true)")
+        .bind(id.to_string())
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Begin a transaction with the RLS context for `user_id` already set
+///
+/// For the authenticated user of the current request this is an operation on
+/// the request's shared transaction; otherwise (background jobs, other users,
+/// or while the shared transaction is held by an operation still open) a
+/// transaction of its own.
+pub async fn begin_with_rls(pool: &PgPool, user_id: Uuid) -> Result<RlsTransaction, sqlx::Error> {
+    if let Some(user) = scoped_user(user_id) {
+        if let Ok(mut request) = Arc::clone(&user.transaction).try_lock_owned() {
+            request.begin_operation(pool, &user).await?;
+            return Ok(RlsTransaction {
+                inner: RlsTransactionInner::Shared(request),
+            });
+        }
+    }
+
+    begin_detached_with_rls(pool, user_id).await
+}
+
+/// Begin a transaction of its own with the RLS context for `user_id` set
+///
+/// Unlike `begin_with_rls` it never joins the request's transaction, so what
+/// it commits is kept even when the request fails (e.g. a failure record).
+pub async fn begin_detached_with_rls(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<RlsTransaction, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    set_rls_context(&mut tx, user_id).await?;
+    Ok(RlsTransaction {
+        inner: RlsTransactionInner::Own(tx),
+    })
+}
+
+/// Act as a patient signed in to the patient portal
+///
+/// The role is 'PATIENT' and there is no user, so none of the staff policies
+/// match; only the portal policies on `portal_patient_id()` do. Both settings
+/// are transaction-local.
+pub async fn apply_portal_context(
+    conn: &mut PgConnection,
+    patient_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "SELECT set_config('app.current_user_role', 'PATIENT', true), set_config('app.portal_patient_id', $1, true)",
+    )
+    .bind(patient_id.to_string())
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Begin a transaction with the patient portal context for `patient_id` set
+pub async fn begin_portal_transaction(
+    pool: &PgPool,
+    patient_id: Uuid,
+) -> Result<Transaction<'static, Postgres>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    apply_portal_context(&mut tx, patient_id).await?;
+    Ok(tx)
+}
+
+/// Act as the public online booking
+///
+/// The role is 'BOOKING' and there is no user; the booking policies only let
+/// it insert patients and PENDING appointments, and it reads nothing.
+pub async fn apply_booking_context(conn: &mut PgConnection) -> Result<(), sqlx::Error> {
+    sqlx::query("SELECT set_config('app.current_user_role', 'BOOKING', true)")
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_rls_scope() {
+        let user_id = Uuid::new_v4();
+        assert_eq!(current_rls_user(), None);
+        assert!(scoped_user(user_id).is_none());
+
+        let (current, own, other) = with_rls_scope(
+            user_id,
+            async {
+                (
+                    current_rls_user(),
+                    scoped_user(user_id).map(|user| user.user_id),
+                    scoped_user(Uuid::new_v4()).is_some(),
+                )
+            },
+            |_| true,
+        )
+        .await
+        .unwrap();
+        assert_eq!(current, Some(user_id));
+        assert_eq!(own, Some(user_id));
+        assert!(!other);
+
+        assert_eq!(current_rls_user(), None);
+    }
+
+    #[tokio::test]
+    async fn test_rls_scope_shares_role_within_request() {
+        let user_id = Uuid::new_v4();
+
+        with_rls_scope(
+            user_id,
+            async {
+                let first = scoped_user(user_id).unwrap();
+                first.role.set("NURSE".to_string()).unwrap();
+
+                // Later services in the same request reuse the role
+                let second = scoped_user(user_id).unwrap();
+                assert_eq!(second.role.get().map(String::as_str), Some("NURSE"));
+            },
+            |_| true,
+        )
+        .await
+        .unwrap();
+
+        // A new request reads it again
+        with_rls_scope(
+            user_id,
+            async {
+                assert!(scoped_user(user_id).unwrap().role.get().is_none());
+            },
+            |_| true,
+        )
+        .await
+        .unwrap();
+    }
+}
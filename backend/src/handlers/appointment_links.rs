@@ -0,0 +1,104 @@
+/*!
+ * Appointment Link Handlers
+ *
+ * Public endpoint behind the confirm and cancel links of appointment
+ * reminders. There is no sign-in: the signed link token names the
+ * appointment, the patient and the action, and expires when the appointment
+ * starts.
+ *
+ * Endpoints:
+ * - POST /api/v1/appointment-links/respond - Confirm or cancel from a reminder link
+ */
+
+use axum::{extract::State, Extension, Json};
+use chrono::Utc;
+use validator::Validate;
+
+use crate::{
+    handlers::auth::AppState,
+    models::{
+        AppointmentLinkAction, AppointmentLinkRequest, AppointmentLinkResponse, AuditAction,
+        AuditLog, CreateAuditLog, EntityType, RequestContext,
+    },
+    services::{AppointmentService, NotificationService},
+    utils::{AppError, Result},
+};
+
+/// Confirm or cancel an appointment from a reminder link
+///
+/// POST /api/v1/appointment-links/respond
+///
+/// Using a link again answers with the appointment as it is. Cancelling
+/// emails the provider.
+pub async fn respond_to_appointment_link(
+    State(state): State<AppState>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Json(req): Json<AppointmentLinkRequest>,
+) -> Result<Json<AppointmentLinkResponse>> {
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let encryption_key = state
+        .encryption_key
+        .as_ref()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+
+    let claims =
+        NotificationService::verify_appointment_link_token(encryption_key, &req.token, Utc::now())
+            .ok_or_else(|| AppError::NotFound("This link is invalid or has expired".to_string()))?;
+
+    let outcome = AppointmentService::new(state.pool.clone(), encryption_key.clone())
+        .respond_to_link(&claims)
+        .await
+        .map_err(|e| {
+            let message = e.to_string();
+            if message.contains("not found") {
+                AppError::NotFound("This link is invalid or has expired".to_string())
+            } else if message.contains("cannot be") {
+                AppError::Conflict(message)
+            } else {
+                AppError::Internal(message)
+            }
+        })?;
+
+    // No staff user: the patient is in the payload
+    if outcome.changed {
+        let _ = AuditLog::create(
+            &state.pool,
+            CreateAuditLog {
+                user_id: None,
+                action: AuditAction::Update,
+                entity_type: EntityType::Appointment,
+                entity_id: Some(outcome.appointment_id.to_string()),
+                changes: Some(serde_json::json!({
+                    "status": outcome.status,
+                    "source": "reminder_link",
+                    "patient_id": claims.patient_id,
+                })),
+                ip_address: request_ctx.ip_address.clone(),
+                user_agent: request_ctx.user_agent.clone(),
+                request_id: Some(request_ctx.request_id),
+            },
+        )
+        .await;
+    }
+
+    let message = match (claims.action, outcome.changed) {
+        (AppointmentLinkAction::Confirm, true) => "Thank you, your appointment is confirmed.",
+        (AppointmentLinkAction::Confirm, false) => "Your appointment was already confirmed.",
+        (AppointmentLinkAction::Cancel, true) => {
+            "Your appointment has been cancelled. Please contact us if you would like to book another one."
+        }
+        (AppointmentLinkAction::Cancel, false) => "Your appointment was already cancelled.",
+    };
+
+    Ok(Json(AppointmentLinkResponse {
+        action: claims.action,
+        status: outcome.status,
+        scheduled_start: outcome.scheduled_start,
+        scheduled_end: outcome.scheduled_end,
+        appointment_type: outcome.appointment_type,
+        provider_name: outcome.provider_name,
+        message: message.to_string(),
+    }))
+}
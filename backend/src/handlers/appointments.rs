@@ -6,8 +6,8 @@
 
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
-    response::IntoResponse,
+    http::{header, HeaderMap, HeaderName, StatusCode},
+    response::{IntoResponse, Response},
     Extension, Json,
 };
 use chrono::{DateTime, Utc};
@@ -16,52 +16,43 @@ use uuid::Uuid;
 use validator::Validate;
 
 use crate::{
-    handlers::auth::AppState,
+    db::rls::apply_rls_context,
+    handlers::{auth::AppState, televisits::provision_televisit},
     models::{
         AppointmentDto, AppointmentSearchFilter, AppointmentStatus,
         AppointmentType, AvailabilityResponse, CancelAppointmentRequest,
-        CreateAppointmentRequest, Patient, RequestContext, UpdateAppointmentRequest, UserRole,
+        CreateAppointmentRequest, MultiProviderAvailabilityResponse, Patient, RequestContext,
+        UpdateAppointmentRequest, UserRole, earliest_slots,
     },
     services::{AppointmentService, NotificationService},
-    utils::{AppError, Result},
+    utils::{
+        concurrency::{expected_version, version_tag},
+        AppError, Result,
+    },
 };
 
+/// `ETag` of an appointment, for `If-Match` on the next update
+fn etag_header(appointment: &AppointmentDto) -> [(HeaderName, String); 1] {
+    [(header::ETAG, version_tag(appointment.updated_at))]
+}
+
 /// Helper function to set RLS context in a transaction
 async fn set_rls_in_transaction(
     tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
     user_id: &Uuid,
     user_role: &UserRole,
 ) -> Result<()> {
-    let role_str = match user_role {
-        UserRole::Admin => "ADMIN",
-        UserRole::Doctor => "DOCTOR",
-    };
-
-    // Use set_config() which supports parameterized queries for security
-    sqlx::query("SELECT set_config('app.current_user_id', $1, true)")
-        .bind(user_id.to_string())
-        .execute(&mut **tx)
-        .await
-        .map_err(|e| {
-            tracing::error!("Failed to set RLS user context: {}", e);
-            AppError::Internal("Failed to set security context".to_string())
-        })?;
-
-    sqlx::query("SELECT set_config('app.current_user_role', $1, true)")
-        .bind(role_str)
-        .execute(&mut **tx)
+    apply_rls_context(&mut **tx, *user_id, &user_role.to_string())
         .await
         .map_err(|e| {
-            tracing::error!("Failed to set RLS role context: {}", e);
+            tracing::error!("Failed to set RLS context: {}", e);
             AppError::Internal("Failed to set security context".to_string())
-        })?;
-
-    Ok(())
+        })
 }
 
 /// Check if user has permission for appointments
 #[cfg(feature = "rbac")]
-async fn check_permission(
+pub(crate) async fn check_permission(
     state: &AppState,
     user_role: &UserRole,
     action: &str,
@@ -88,7 +79,7 @@ async fn check_permission(
 }
 
 #[cfg(not(feature = "rbac"))]
-async fn check_permission(
+pub(crate) async fn check_permission(
     _state: &AppState,
     user_role: &UserRole,
     action: &str,
@@ -103,6 +94,20 @@ async fn check_permission(
                 ));
             }
         }
+        "manage_overbooking" => {
+            if !matches!(user_role, UserRole::Admin) {
+                return Err(AppError::Forbidden(
+                    "Only administrators can manage overbooking policies".to_string(),
+                ));
+            }
+        }
+        "manage_resources" => {
+            if !matches!(user_role, UserRole::Admin) {
+                return Err(AppError::Forbidden(
+                    "Only administrators can manage rooms and equipment".to_string(),
+                ));
+            }
+        }
         _ => {
             // ADMIN and DOCTOR can perform other actions
             if !matches!(user_role, UserRole::Admin | UserRole::Doctor) {
@@ -118,50 +123,137 @@ async fn check_permission(
 /// Query parameters for availability check
 #[derive(Debug, Deserialize, Validate)]
 pub struct AvailabilityQuery {
+    /// Provider to check; without it, the providers are searched together
     #[validate(custom(function = "crate::utils::validate_uuid"))]
-    pub provider_id: String,
+    pub provider_id: Option<String>,
+    /// Providers to search, comma-separated (default: all active doctors)
+    pub provider_ids: Option<String>,
     pub date: DateTime<Utc>,
     #[validate(range(min = 15, max = 480))]
     pub duration_minutes: i32,
+    /// Type of the appointment to book, so its buffers are left free too
+    #[serde(rename = "type")]
+    pub appointment_type: Option<AppointmentType>,
+    /// Resources to reserve, comma-separated
+    pub resource_ids: Option<String>,
+    /// Most merged slots returned when searching several providers
+    #[validate(range(min = 1, max = 100))]
+    pub limit: Option<usize>,
+}
+
+/// Merged slots returned by a search across providers unless `limit` is given
+const DEFAULT_EARLIEST_SLOTS: usize = 20;
+
+/// Parse a comma-separated list of IDs from a query parameter
+fn parse_id_list(value: Option<&str>, what: &str) -> Result<Vec<Uuid>> {
+    value
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(Uuid::parse_str)
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|_| AppError::BadRequest(format!("Invalid {} ID", what)))
 }
 
 /// POST /api/v1/appointments/availability
 ///
 /// Check appointment availability for a provider
+///
+/// Without `provider_id`, searches the doctors in `provider_ids` (all the
+/// active ones by default) and also returns their free slots merged,
+/// earliest first.
 pub async fn check_availability(
     State(state): State<AppState>,
     Extension(_user_id): Extension<Uuid>,
     Extension(user_role): Extension<UserRole>,
     Query(query): Query<AvailabilityQuery>,
-) -> Result<impl IntoResponse> {
+) -> Result<Response> {
     check_permission(&state, &user_role, "read").await?;
 
     query.validate().map_err(|e| {
         AppError::BadRequest(format!("Validation error: {}", e))
     })?;
 
-    let provider_id = Uuid::parse_str(&query.provider_id)
-        .map_err(|_| AppError::BadRequest("Invalid provider ID".to_string()))?;
+    let resource_ids = parse_id_list(query.resource_ids.as_deref(), "resource")?;
+
+    let encryption_key = state
+        .encryption_key
+        .as_ref()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+    let service = AppointmentService::new(state.pool.clone(), encryption_key.clone());
+
+    let Some(provider_id) = query.provider_id.as_deref() else {
+        let provider_ids = parse_id_list(query.provider_ids.as_deref(), "provider")?;
+
+        let providers = service
+            .check_availability_for_providers(
+                &provider_ids,
+                query.date,
+                query.duration_minutes,
+                query.appointment_type,
+                &resource_ids,
+            )
+            .await
+            .map_err(|e| match e.downcast::<AppError>() {
+                // Unknown provider or resource
+                Ok(app_error) => app_error,
+                Err(e) => AppError::Internal(e.to_string()),
+            })?;
+
+        let response = MultiProviderAvailabilityResponse {
+            date: query.date,
+            earliest_slots: earliest_slots(
+                &providers,
+                query.limit.unwrap_or(DEFAULT_EARLIEST_SLOTS),
+            ),
+            providers,
+        };
+
+        return Ok((StatusCode::OK, Json(response)).into_response());
+    };
+
+    if query.provider_ids.is_some() {
+        return Err(AppError::BadRequest(
+            "Use either provider_id or provider_ids".to_string(),
+        ));
+    }
 
-    let service = AppointmentService::new(state.pool.clone());
+    let provider_id = Uuid::parse_str(provider_id)
+        .map_err(|_| AppError::BadRequest("Invalid provider ID".to_string()))?;
 
-    let slots = service
-        .check_availability(provider_id, query.date, query.duration_minutes)
+    let (slots, bottleneck_resource_id) = service
+        .check_availability(
+            provider_id,
+            query.date,
+            query.duration_minutes,
+            query.appointment_type,
+            &resource_ids,
+        )
         .await
-        .map_err(|e| AppError::Internal(e.to_string()))?;
+        .map_err(|e| match e.downcast::<AppError>() {
+            // Unknown or inactive resource
+            Ok(app_error) => app_error,
+            Err(e) => AppError::Internal(e.to_string()),
+        })?;
 
     let response = AvailabilityResponse {
         date: query.date,
         provider_id,
         slots,
+        bottleneck_resource_id,
     };
 
-    Ok((StatusCode::OK, Json(response)))
+    Ok((StatusCode::OK, Json(response)).into_response())
 }
 
 /// POST /api/v1/appointments
 ///
 /// Create a new appointment
+///
+/// With `overbook`, the appointment may be booked over the provider's
+/// other appointments as far as their overbooking policy allows; this needs
+/// the `overbook` permission.
 pub async fn create_appointment(
     State(state): State<AppState>,
     Extension(user_id): Extension<Uuid>,
@@ -170,6 +262,9 @@ pub async fn create_appointment(
     Json(req): Json<CreateAppointmentRequest>,
 ) -> Result<impl IntoResponse> {
     check_permission(&state, &user_role, "create").await?;
+    if req.overbook {
+        check_permission(&state, &user_role, "overbook").await?;
+    }
 
     req.validate().map_err(|e| {
         AppError::BadRequest(format!("Validation error: {}", e))
@@ -182,12 +277,28 @@ pub async fn create_appointment(
     let scheduled_start = req.scheduled_start;
     let appointment_type = req.appointment_type.clone();
 
-    let service = AppointmentService::new(state.pool.clone());
+    // A televisit needs a video room, provisioned right after creation
+    if appointment_type == AppointmentType::Televisit && state.video_room_provider.is_none() {
+        return Err(AppError::BadRequest(
+            "Televisits are not configured (VIDEO_PROVIDER)".to_string(),
+        ));
+    }
+
+    let encryption_key = state
+        .encryption_key
+        .as_ref()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+    let service = AppointmentService::new(state.pool.clone(), encryption_key.clone());
 
     let appointment = service
         .create_appointment(req, user_id, Some(&request_ctx))
         .await
         .map_err(|e| {
+            let e = match e.downcast::<AppError>() {
+                // Time not available: conflicts and alternatives
+                Ok(app_error) => return app_error,
+                Err(e) => e,
+            };
             let error_str = e.to_string();
             let error_lower = error_str.to_lowercase();
             if error_lower.contains("conflict") {
@@ -200,6 +311,17 @@ pub async fn create_appointment(
             }
         })?;
 
+    // Provision the video room and email the join link; if the provider
+    // fails the appointment stays, and staff provision again later
+    if appointment_type == AppointmentType::Televisit {
+        if let Err(e) = provision_televisit(&state, appointment.id, false, user_id).await {
+            tracing::warn!(
+                "Failed to provision televisit room for appointment {}: {}",
+                appointment.id, e
+            );
+        }
+    }
+
     // Send confirmation notification if requested and email service is available
     if send_notification {
         if let Some(ref email_service) = state.email_service {
@@ -309,7 +431,11 @@ pub async fn get_appointment(
 ) -> Result<impl IntoResponse> {
     check_permission(&state, &user_role, "read").await?;
 
-    let service = AppointmentService::new(state.pool.clone());
+    let encryption_key = state
+        .encryption_key
+        .as_ref()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+    let service = AppointmentService::new(state.pool.clone(), encryption_key.clone());
 
     let appointment = service
         .get_appointment(id, Some(user_id), Some(&request_ctx))
@@ -317,31 +443,44 @@ pub async fn get_appointment(
         .map_err(|e| AppError::Internal(e.to_string()))?
         .ok_or_else(|| AppError::NotFound("Appointment not found".to_string()))?;
 
-    Ok((StatusCode::OK, Json(appointment)))
+    Ok((StatusCode::OK, etag_header(&appointment), Json(appointment)))
 }
 
 /// PUT /api/v1/appointments/:id
 ///
 /// Update an appointment
+///
+/// With `If-Match` or `expected_version`, an appointment changed since is
+/// not updated: 409 with the current appointment. `overbook` works as when
+/// creating.
 pub async fn update_appointment(
     State(state): State<AppState>,
     Extension(user_id): Extension<Uuid>,
     Extension(user_role): Extension<UserRole>,
     Extension(request_ctx): Extension<RequestContext>,
     Path(id): Path<Uuid>,
-    Json(req): Json<UpdateAppointmentRequest>,
+    headers: HeaderMap,
+    Json(mut req): Json<UpdateAppointmentRequest>,
 ) -> Result<impl IntoResponse> {
     check_permission(&state, &user_role, "update").await?;
+    if req.overbook {
+        check_permission(&state, &user_role, "overbook").await?;
+    }
 
     req.validate().map_err(|e| {
         AppError::BadRequest(format!("Validation error: {}", e))
     })?;
+    req.expected_version = expected_version(&headers, req.expected_version)?;
 
     // Extract notification flag and check if this is a confirmation
     let send_notification = req.send_notification.unwrap_or(false);
     let is_confirming = req.status == Some(AppointmentStatus::Confirmed);
 
-    let service = AppointmentService::new(state.pool.clone());
+    let encryption_key = state
+        .encryption_key
+        .as_ref()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+    let service = AppointmentService::new(state.pool.clone(), encryption_key.clone());
 
     // Get appointment details before update for notification (if confirming)
     let existing_appointment = if send_notification && is_confirming {
@@ -354,6 +493,12 @@ pub async fn update_appointment(
         .update_appointment(id, req, user_id, Some(&request_ctx))
         .await
         .map_err(|e| {
+            let e = match e.downcast::<AppError>() {
+                // Edited by someone else since the client read it, or the
+                // new time is not available
+                Ok(app_error) => return app_error,
+                Err(e) => e,
+            };
             if e.to_string().contains("conflict") {
                 AppError::Conflict(e.to_string())
             } else if e.to_string().contains("not found") {
@@ -377,7 +522,7 @@ pub async fn update_appointment(
                         "Skipping confirmation notification for appointment {} - patient {} has email notifications disabled",
                         id, existing.patient_id
                     );
-                    return Ok((StatusCode::OK, Json(appointment)));
+                    return Ok((StatusCode::OK, etag_header(&appointment), Json(appointment)));
                 }
 
                 // Get encryption key for patient data decryption
@@ -385,7 +530,7 @@ pub async fn update_appointment(
                     Some(key) => key.clone(),
                     None => {
                         tracing::warn!("Cannot send confirmation notification: encryption key not configured");
-                        return Ok((StatusCode::OK, Json(appointment)));
+                        return Ok((StatusCode::OK, etag_header(&appointment), Json(appointment)));
                     }
                 };
 
@@ -451,7 +596,7 @@ pub async fn update_appointment(
         }
     }
 
-    Ok((StatusCode::OK, Json(appointment)))
+    Ok((StatusCode::OK, etag_header(&appointment), Json(appointment)))
 }
 
 /// DELETE /api/v1/appointments/:id
@@ -475,7 +620,11 @@ pub async fn cancel_appointment(
     let send_notification = req.send_notification.unwrap_or(false);
     let cancellation_reason = req.cancellation_reason.clone();
 
-    let service = AppointmentService::new(state.pool.clone());
+    let encryption_key = state
+        .encryption_key
+        .as_ref()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+    let service = AppointmentService::new(state.pool.clone(), encryption_key.clone());
 
     // Get appointment details before cancellation for notification
     let existing_appointment = if send_notification {
@@ -638,7 +787,11 @@ pub async fn list_appointments(
         offset: query.offset,
     };
 
-    let service = AppointmentService::new(state.pool.clone());
+    let encryption_key = state
+        .encryption_key
+        .as_ref()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+    let service = AppointmentService::new(state.pool.clone(), encryption_key.clone());
 
     let (appointments, total) = service
         .list_appointments(filter, Some(user_id), Some(&request_ctx))
@@ -681,7 +834,11 @@ pub async fn get_daily_schedule(
     let provider_id = Uuid::parse_str(&query.provider_id)
         .map_err(|_| AppError::BadRequest("Invalid provider ID".to_string()))?;
 
-    let service = AppointmentService::new(state.pool.clone());
+    let encryption_key = state
+        .encryption_key
+        .as_ref()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+    let service = AppointmentService::new(state.pool.clone(), encryption_key.clone());
 
     let appointments = service
         .get_daily_schedule(provider_id, query.date)
@@ -709,7 +866,11 @@ pub async fn get_weekly_schedule(
     let provider_id = Uuid::parse_str(&query.provider_id)
         .map_err(|_| AppError::BadRequest("Invalid provider ID".to_string()))?;
 
-    let service = AppointmentService::new(state.pool.clone());
+    let encryption_key = state
+        .encryption_key
+        .as_ref()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+    let service = AppointmentService::new(state.pool.clone(), encryption_key.clone());
 
     let appointments = service
         .get_weekly_schedule(provider_id, query.date)
@@ -737,7 +898,11 @@ pub async fn get_monthly_schedule(
     let provider_id = Uuid::parse_str(&query.provider_id)
         .map_err(|_| AppError::BadRequest("Invalid provider ID".to_string()))?;
 
-    let service = AppointmentService::new(state.pool.clone());
+    let encryption_key = state
+        .encryption_key
+        .as_ref()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+    let service = AppointmentService::new(state.pool.clone(), encryption_key.clone());
 
     let appointments = service
         .get_monthly_schedule(provider_id, query.date)
@@ -757,7 +922,11 @@ pub async fn get_statistics(
 ) -> Result<impl IntoResponse> {
     check_permission(&state, &user_role, "read").await?;
 
-    let service = AppointmentService::new(state.pool.clone());
+    let encryption_key = state
+        .encryption_key
+        .as_ref()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+    let service = AppointmentService::new(state.pool.clone(), encryption_key.clone());
 
     let stats = service
         .get_statistics()
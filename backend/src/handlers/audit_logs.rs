@@ -10,6 +10,10 @@
  * - GET /api/v1/audit-logs/statistics - Summary statistics
  * - GET /api/v1/audit-logs/user/:user_id/activity - User activity summary
  * - GET /api/v1/audit-logs/export - Export logs to CSV/JSON
+ * - GET /api/v1/audit-logs/export/stream - Stream all matching logs as CSV
+ * - GET /api/v1/audit-logs/activity-report - Per-user activity for access reviews
+ * - GET /api/v1/audit-logs/verify - Verify the tamper-evident hash chain
+ * - GET /api/v1/patients/:id/access-log - Who accessed a patient's chart
  */
 
 use axum::{
@@ -23,12 +27,12 @@ use uuid::Uuid;
 use crate::{
     handlers::auth::AppState,
     models::audit_log::{
-        AuditAction, AuditLogResponse, AuditLogStatistics, AuditLogsFilter,
+        AuditAction, AuditChainReport, AuditLogResponse, PatientAccessLog, PatientAccessLogFilter, AuditLogStatistics, AuditLogsFilter,
         EntityType, ExportAuditLogsRequest, ExportFormat, ListAuditLogsResponse,
-        UserActivitySummary,
+        UserActivityReport, UserActivityReportFilter, UserActivitySummary,
     },
     models::user::UserRole,
-    services::AuditLogService,
+    services::{AuditChainService, AuditLogService, ReportExportService},
 };
 
 #[cfg(feature = "rbac")]
@@ -265,6 +269,148 @@ pub async fn export_audit_logs(
     }
 }
 
+/// Stream every matching audit log as CSV
+///
+/// GET /api/v1/audit-logs/export/stream
+///
+/// Query parameters:
+/// - All filter parameters from list endpoint (paging and sorting are ignored)
+///
+/// Unlike the export endpoint there is no row limit: logs are written into the
+/// response (chunked transfer encoding) as they are read, newest first.
+///
+/// Returns: File download (CSV format)
+pub async fn stream_audit_logs_export(
+    State(state): State<AppState>,
+    Extension(user_role): Extension<UserRole>,
+    Query(filter): Query<AuditLogsFilter>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    #[cfg(feature = "rbac")]
+    require_admin(&user_role)?;
+
+    let rows = AuditLogService::new(state.pool.clone()).stream_logs(filter);
+    let body = ReportExportService::stream_csv(rows).map_err(|e| {
+        (
+            StatusCode::NOT_IMPLEMENTED,
+            Json(serde_json::json!({
+                "error": "Streamed export is not available",
+                "message": e.to_string()
+            })),
+        )
+    })?;
+
+    let filename = format!(
+        "audit_logs_export_{}.csv",
+        chrono::Utc::now().format("%Y%m%d_%H%M%S")
+    );
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
+            (
+                header::CONTENT_DISPOSITION,
+                &format!("attachment; filename=\"{}\"", filename),
+            ),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+/// Per-user activity report for periodic access reviews
+///
+/// GET /api/v1/audit-logs/activity-report
+///
+/// Query parameters:
+/// - start_date: Period start (YYYY-MM-DD, default: 30 days before end)
+/// - end_date: Period end (YYYY-MM-DD, default: today)
+/// - role: Only include users with this role
+///
+/// Returns per-user logins, distinct patients accessed, documents generated
+/// and after-hours requests, plus totals per role. Days are Europe/Rome days.
+pub async fn get_user_activity_report(
+    State(state): State<AppState>,
+    Extension(user_role): Extension<UserRole>,
+    Query(filter): Query<UserActivityReportFilter>,
+) -> Result<Json<UserActivityReport>, (StatusCode, Json<serde_json::Value>)> {
+    #[cfg(feature = "rbac")]
+    require_admin(&user_role)?;
+
+    let today = chrono::Utc::now().with_timezone(&chrono_tz::Europe::Rome).date_naive();
+    let (start, end) = filter.period(today).map_err(|message| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": "Invalid period",
+                "message": message
+            })),
+        )
+    })?;
+
+    let service = AuditLogService::new(state.pool.clone());
+
+    match service
+        .get_user_activity_report(start, end, today, filter.role.as_deref())
+        .await
+    {
+        Ok(report) => Ok(Json(report)),
+        Err(e) => {
+            tracing::error!("Failed to build user activity report: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "error": "Failed to build user activity report",
+                    "message": e.to_string()
+                })),
+            ))
+        }
+    }
+}
+
+/// Get the record access log of a patient
+///
+/// GET /api/v1/patients/:id/access-log
+///
+/// Query parameters:
+/// - date_from: Accesses from this date (YYYY-MM-DD, Europe/Rome)
+/// - date_to: Accesses until this date (YYYY-MM-DD, Europe/Rome)
+/// - user_id: Only accesses by this user
+/// - page: Page number (default: 1)
+/// - page_size: Items per page (default: 50, max: 100)
+///
+/// Answers "who accessed my record" requests: every request that viewed,
+/// modified or exported the patient or one of their visits, diagnoses,
+/// prescriptions, appointments, documents or notifications, plus totals per user.
+pub async fn get_patient_access_log(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(user_role): Extension<UserRole>,
+    Path(patient_id): Path<Uuid>,
+    Query(filter): Query<PatientAccessLogFilter>,
+) -> Result<Json<PatientAccessLog>, (StatusCode, Json<serde_json::Value>)> {
+    #[cfg(feature = "rbac")]
+    require_admin(&user_role)?;
+
+    let service = AuditLogService::new(state.pool.clone());
+
+    match service
+        .get_patient_access_log(patient_id, filter, user_id)
+        .await
+    {
+        Ok(log) => Ok(Json(log)),
+        Err(e) => {
+            tracing::error!("Failed to get patient access log: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "error": "Failed to get patient access log",
+                    "message": e.to_string()
+                })),
+            ))
+        }
+    }
+}
+
 /// Get available filter options (action types and entity types)
 ///
 /// GET /api/v1/audit-logs/filter-options
@@ -281,3 +427,45 @@ pub async fn get_filter_options(
         "entity_types": EntityType::all()
     })))
 }
+
+/// Verify the tamper-evident audit log hash chain
+///
+/// GET /api/v1/audit-logs/verify
+///
+/// Recomputes the SHA-256 chain over all chained audit logs and reports
+/// gaps, broken links, modified rows and a chain that ends before the
+/// recorded head. Scans the whole table, so it can take a while on large
+/// installations.
+///
+/// Returns: AuditChainReport (valid = false when any issue was found)
+pub async fn verify_audit_chain(
+    State(state): State<AppState>,
+    Extension(user_role): Extension<UserRole>,
+) -> Result<Json<AuditChainReport>, (StatusCode, Json<serde_json::Value>)> {
+    #[cfg(feature = "rbac")]
+    require_admin(&user_role)?;
+
+    let service = AuditChainService::new(state.pool.clone());
+
+    match service.verify().await {
+        Ok(report) => {
+            if !report.valid {
+                tracing::error!(
+                    "Audit log chain verification found {} issues",
+                    report.issue_count
+                );
+            }
+            Ok(Json(report))
+        }
+        Err(e) => {
+            tracing::error!("Failed to verify audit log chain: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "error": "Failed to verify audit log chain",
+                    "message": e.to_string()
+                })),
+            ))
+        }
+    }
+}
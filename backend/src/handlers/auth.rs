@@ -1,18 +1,38 @@
 /*!
  * Authentication HTTP Handlers
  *
- * Handles HTTP requests for authentication endpoints (login, refresh, logout).
+ * Handles HTTP requests for authentication endpoints (login, refresh, logout,
+ * password change, token introspection/revocation, JWKS).
  */
 
-use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use axum_extra::extract::cookie::CookieJar;
 use serde::{Deserialize, Serialize};
 
 use axum::Extension;
 use crate::{
-    middleware::session_timeout::SessionManager,
-    models::{AuditAction, AuditLog, CreateAuditLog, EntityType, RequestContext},
-    services::{AuthService, EmailService, LoginRequest, LoginResponse, SettingsService, TokenPair},
-    utils::{EncryptionKey, Result},
+    middleware::{
+        session_timeout::SessionManager,
+        ip_allowlist::AdminIpAllowlist,
+        rate_limit::{LoginThrottle, RateLimitLayer, UNKNOWN_CLIENT},
+        token_denylist::{expiry_from_claim, RevokedToken, TokenDenylist},
+    },
+    models::{
+        impersonation::Impersonation,
+        trusted_device::{TrustedDeviceResponse, TRUSTED_DEVICE_COOKIE, TRUSTED_DEVICE_DAYS},
+        AuditAction, AuditLog, AuthUser, CreateAuditLog, EntityType, RequestContext, User, UserRole,
+    },
+    services::{
+        ActorClaim, AuthService, Claims, EmailService, LoginRequest, LoginResponse, PasswordPolicy,
+        PasswordPolicyService, ReferenceCache, SettingsService, SistemaTsClient, TokenPair,
+        TrustedDeviceService, VideoRoomProvider,
+    },
+    utils::{AppError, EncryptionKey, PasswordHasherUtil, Result},
 };
 use sqlx::PgPool;
 use std::sync::Arc;
@@ -26,11 +46,25 @@ pub struct AppState {
     pub pool: PgPool,
     pub auth_service: AuthService,
     pub session_manager: SessionManager,
+    /// Revoked token IDs rejected by the auth middleware
+    pub token_denylist: TokenDenylist,
+    /// Failed login tracking per client IP and IP+username
+    pub login_throttle: LoginThrottle,
+    /// Request quotas per user and client IP
+    pub rate_limiter: RateLimitLayer,
+    /// CIDR ranges allowed to reach admin routes
+    pub admin_ip_allowlist: AdminIpAllowlist,
     pub encryption_key: Option<EncryptionKey>,
     /// Email service for document delivery (optional - None if not configured)
     pub email_service: Option<EmailService>,
+    /// Sistema TS e-prescription client (optional - None if not configured)
+    pub sistema_ts: Option<SistemaTsClient>,
+    /// Video rooms for televisits (optional - None if not configured)
+    pub video_room_provider: Option<Arc<dyn VideoRoomProvider>>,
     /// Settings service with in-memory cache (shared across requests)
     pub settings_service: Arc<SettingsService>,
+    /// Cache of reference data (settings, medication search, document templates)
+    pub reference_cache: ReferenceCache,
     /// Server start time for uptime calculation
     pub start_time: std::time::SystemTime,
     /// Current environment (development/production)
@@ -49,7 +83,9 @@ pub struct AppState {
 /// {
 ///   "username": "doctor1",
 ///   "password": "secure_password",
-///   "mfa_code": "123456" // Optional, required if MFA is enabled
+///   "mfa_code": "123456", // Optional, required if MFA is enabled
+///   "captcha_token": "...", // Optional, required after repeated failures (see GET /auth/captcha)
+///   "remember_device": true // Optional, with mfa_code: skip MFA on this device for 30 days
 /// }
 /// ```
 ///
@@ -74,11 +110,46 @@ pub struct AppState {
 pub async fn login_handler(
     State(state): State<AppState>,
     Extension(request_ctx): Extension<RequestContext>,
+    jar: CookieJar,
     Json(login_req): Json<LoginRequest>,
-) -> Result<Json<LoginResponse>> {
+) -> Result<Response> {
     tracing::info!("Login attempt for user: {}", login_req.username);
 
-    let mut response = state.auth_service.login(&state.pool, login_req, Some(&request_ctx)).await?;
+    // Require a CAPTCHA once this IP or this username has accumulated recent
+    // failed logins
+    if let Some(captcha) = state.auth_service.captcha() {
+        let ip = request_ctx.ip_address.as_deref();
+        let recent_failures = state
+            .login_throttle
+            .recent_failures(ip.unwrap_or(UNKNOWN_CLIENT), &login_req.username)
+            .await;
+        if captcha.is_required(recent_failures) {
+            let token = login_req
+                .captcha_token
+                .as_deref()
+                .map(str::trim)
+                .filter(|t| !t.is_empty())
+                .ok_or_else(|| {
+                    AppError::CaptchaRequired("CAPTCHA verification is required".to_string())
+                })?;
+
+            if !captcha.verify(token, ip).await {
+                tracing::warn!(
+                    "Login rejected for {}: CAPTCHA verification failed",
+                    ip.unwrap_or(UNKNOWN_CLIENT)
+                );
+                return Err(AppError::CaptchaRequired(
+                    "CAPTCHA verification failed".to_string(),
+                ));
+            }
+        }
+    }
+
+    let device_token = jar.get(TRUSTED_DEVICE_COOKIE).map(|c| c.value().to_string());
+    let mut response = state
+        .auth_service
+        .login(&state.pool, login_req, device_token.as_deref(), Some(&request_ctx))
+        .await?;
 
     // Check if global MFA requirement is enabled and user hasn't set up MFA
     // Only check if login was successful and user doesn't already need MFA verification
@@ -100,11 +171,48 @@ pub async fn login_handler(
         }
     }
 
+    // Flag expired passwords once credentials (and MFA, if enabled) are verified
+    if !response.requires_mfa {
+        let policy = PasswordPolicy::load(&state.settings_service).await;
+        let expired = PasswordPolicyService::new(state.pool.clone())
+            .is_password_expired(response.user.id, &policy)
+            .await
+            .unwrap_or(false);
+
+        if expired {
+            tracing::info!(
+                "Password change required for user {} (password older than {} days)",
+                response.user.username,
+                policy.max_age_days
+            );
+            response.requires_password_change = true;
+        }
+    }
+
     // Track session activity on successful login
     state.session_manager.track_activity(&response.user.id);
     tracing::debug!("Session activity tracked for user: {}", response.user.id);
 
-    Ok(Json(response))
+    match response.trusted_device_token.take() {
+        Some(token) => Ok((
+            [(header::SET_COOKIE, trusted_device_cookie(&state, &token, TRUSTED_DEVICE_DAYS * 86400))],
+            Json(response),
+        )
+            .into_response()),
+        None => Ok(Json(response).into_response()),
+    }
+}
+
+/// Set-Cookie value for the trusted device cookie (`max_age` 0 clears it)
+///
+/// Scoped to the auth routes and not readable from JavaScript; `Secure`
+/// outside development.
+fn trusted_device_cookie(state: &AppState, token: &str, max_age: i64) -> String {
+    let secure = if state.environment == "development" { "" } else { "; Secure" };
+    format!(
+        "{}={}; Path=/api/v1/auth; Max-Age={}; HttpOnly; SameSite=Strict{}",
+        TRUSTED_DEVICE_COOKIE, token, max_age, secure
+    )
 }
 
 /// Refresh token request
@@ -145,6 +253,11 @@ pub async fn refresh_token_handler(
     let user_id = uuid::Uuid::parse_str(&claims.sub)
         .map_err(|_| crate::utils::AppError::Unauthorized("Invalid user ID in token".to_string()))?;
 
+    if state.token_denylist.is_revoked(&claims.jti) {
+        tracing::warn!("Token refresh rejected: refresh token {} has been revoked", claims.jti);
+        return Err(crate::utils::AppError::Unauthorized("Token has been revoked".to_string()));
+    }
+
     // Reject refresh if session was invalidated (AUTH-VULN-04: token revocation on logout)
     if !state.session_manager.is_session_active(&user_id) {
         tracing::warn!("Token refresh rejected: session invalidated for user {}", user_id);
@@ -207,6 +320,25 @@ pub async fn logout_handler(
         if let Ok(claims) = state.auth_service.validate_token(&token) {
             let user_id = uuid::Uuid::parse_str(&claims.sub)
                 .map_err(|_| crate::utils::AppError::Unauthorized("Invalid user ID in token".to_string()))?;
+
+            // Logging out of an impersonation session only ends the impersonation;
+            // the impersonated user's own session stays active
+            if let Some(impersonator_id) = claims.impersonator_id() {
+                let impersonation = Impersonation {
+                    impersonator_id,
+                    jti: claims.jti.clone(),
+                    expires_at: expiry_from_claim(claims.exp),
+                };
+                super::impersonation::end_session(&state, &impersonation).await;
+                tracing::info!("Impersonation of user {} ended by logout", user_id);
+                return Ok((
+                    StatusCode::OK,
+                    Json(LogoutResponse {
+                        message: "Logged out successfully".to_string(),
+                    }),
+                ));
+            }
+
             state.session_manager.invalidate_session(&user_id);
             tracing::info!("Session invalidated for user: {}", user_id);
 
@@ -236,6 +368,412 @@ pub async fn logout_handler(
     ))
 }
 
+/// Change password request
+#[derive(Debug, Deserialize)]
+pub struct ChangePasswordRequest {
+    pub current_password: String,
+    pub new_password: String,
+}
+
+/// Change password handler
+///
+/// POST /api/v1/auth/change-password
+///
+/// Changes the authenticated user's own password. The new password must meet
+/// the complexity rules and must not match any of the last
+/// `security.password_history_count` passwords. Used to clear the
+/// `requiresPasswordChange` login flag once a password has expired.
+///
+/// # Request Body
+///
+/// ```json
+/// {
+///   "current_password": "old_password",
+///   "new_password": "new_secure_password"
+/// }
+/// ```
+pub async fn change_password_handler(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Json(req): Json<ChangePasswordRequest>,
+) -> Result<StatusCode> {
+    let user = User::find_by_id(&state.pool, &auth_user.user_id).await?;
+
+    if !PasswordHasherUtil::verify_password(&req.current_password, &user.password_hash) {
+        return Err(crate::utils::AppError::Unauthorized(
+            "Current password is incorrect".to_string(),
+        ));
+    }
+
+    let policy = PasswordPolicy::load(&state.settings_service).await;
+    PasswordPolicyService::new(state.pool.clone())
+        .set_password(user.id, &req.new_password, &policy)
+        .await?;
+
+    tracing::info!("Password changed for user {}", user.id);
+
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(user.id),
+            action: AuditAction::Update,
+            entity_type: EntityType::User,
+            entity_id: Some(user.id.to_string()),
+            changes: Some(serde_json::json!({
+                "action": "change_password",
+            })),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// List trusted devices handler
+///
+/// GET /api/v1/auth/trusted-devices
+///
+/// Lists the authenticated user's active trusted devices. The device making
+/// the request (if trusted) is flagged with `current: true`.
+pub async fn list_trusted_devices_handler(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    jar: CookieJar,
+) -> Result<Json<Vec<TrustedDeviceResponse>>> {
+    let current_device_id = jar
+        .get(TRUSTED_DEVICE_COOKIE)
+        .and_then(|c| state.auth_service.trusted_device_id(c.value(), auth_user.user_id));
+
+    let devices = TrustedDeviceService::new(state.pool.clone())
+        .list_active(auth_user.user_id)
+        .await?;
+
+    Ok(Json(
+        devices
+            .into_iter()
+            .map(|d| TrustedDeviceResponse::from_device(d, current_device_id))
+            .collect(),
+    ))
+}
+
+/// Revoke trusted device handler
+///
+/// DELETE /api/v1/auth/trusted-devices/{id}
+///
+/// Revokes one of the authenticated user's trusted devices; the next login
+/// from it requires an MFA code again.
+pub async fn revoke_trusted_device_handler(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    jar: CookieJar,
+    Path(device_id): Path<uuid::Uuid>,
+) -> Result<Response> {
+    let revoked = TrustedDeviceService::new(state.pool.clone())
+        .revoke(auth_user.user_id, device_id)
+        .await?;
+
+    if !revoked {
+        return Err(AppError::NotFound("Trusted device not found".to_string()));
+    }
+
+    audit_trusted_device_revocation(&state, &auth_user, &request_ctx, serde_json::json!({
+        "event": "trusted_device_revoked",
+        "device_id": device_id,
+    }))
+    .await;
+
+    let is_current = jar
+        .get(TRUSTED_DEVICE_COOKIE)
+        .and_then(|c| state.auth_service.trusted_device_id(c.value(), auth_user.user_id))
+        == Some(device_id);
+
+    if is_current {
+        Ok((
+            StatusCode::NO_CONTENT,
+            [(header::SET_COOKIE, trusted_device_cookie(&state, "", 0))],
+        )
+            .into_response())
+    } else {
+        Ok(StatusCode::NO_CONTENT.into_response())
+    }
+}
+
+/// Revoke all trusted devices handler
+///
+/// DELETE /api/v1/auth/trusted-devices
+///
+/// Revokes every trusted device of the authenticated user and clears the
+/// device cookie on the calling device.
+pub async fn revoke_all_trusted_devices_handler(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+) -> Result<Response> {
+    let revoked = TrustedDeviceService::new(state.pool.clone())
+        .revoke_all(auth_user.user_id)
+        .await?;
+
+    audit_trusted_device_revocation(&state, &auth_user, &request_ctx, serde_json::json!({
+        "event": "trusted_devices_revoked",
+        "count": revoked,
+    }))
+    .await;
+
+    Ok((
+        StatusCode::NO_CONTENT,
+        [(header::SET_COOKIE, trusted_device_cookie(&state, "", 0))],
+    )
+        .into_response())
+}
+
+async fn audit_trusted_device_revocation(
+    state: &AppState,
+    auth_user: &AuthUser,
+    request_ctx: &RequestContext,
+    changes: serde_json::Value,
+) {
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(auth_user.user_id),
+            action: AuditAction::Delete,
+            entity_type: EntityType::User,
+            entity_id: Some(auth_user.user_id.to_string()),
+            changes: Some(changes),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+}
+
+/// Token introspection/revocation request (RFC 7662 / RFC 7009)
+#[derive(Debug, Deserialize)]
+pub struct TokenRequest {
+    /// The access or refresh token to inspect or revoke
+    pub token: String,
+    /// Optional hint: "access_token" or "refresh_token"
+    pub token_type_hint: Option<String>,
+    /// Optional reason recorded with a revocation
+    pub reason: Option<String>,
+}
+
+/// Token introspection response (RFC 7662)
+///
+/// Only `active` is returned for tokens that are invalid, expired, revoked or
+/// not visible to the caller.
+#[derive(Debug, Serialize, Default)]
+pub struct IntrospectionResponse {
+    pub active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exp: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iat: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
+    /// Impersonating admin (RFC 8693 actor claim)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub act: Option<ActorClaim>,
+}
+
+/// Validate a token and check the caller may see it
+///
+/// Admins may act on any token; other users only on their own.
+fn claims_visible_to(state: &AppState, auth_user: &AuthUser, req: &TokenRequest) -> Option<Claims> {
+    let claims = state
+        .auth_service
+        .validate_any_token(&req.token, req.token_type_hint.as_deref())?;
+
+    let owns_token = claims.sub == auth_user.user_id.to_string();
+    if owns_token || matches!(auth_user.role, UserRole::Admin) {
+        Some(claims)
+    } else {
+        None
+    }
+}
+
+/// Token introspection handler
+///
+/// POST /api/v1/auth/introspect
+///
+/// # Request Body
+///
+/// ```json
+/// {
+///   "token": "jwt_token",
+///   "token_type_hint": "access_token" // Optional
+/// }
+/// ```
+///
+/// # Response
+///
+/// ```json
+/// {
+///   "active": true,
+///   "sub": "uuid",
+///   "role": "DOCTOR",
+///   "token_type": "access",
+///   "exp": 1700001800,
+///   "iat": 1700000000,
+///   "jti": "uuid"
+/// }
+/// ```
+pub async fn introspect_handler(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Json(req): Json<TokenRequest>,
+) -> Json<IntrospectionResponse> {
+    let claims = match claims_visible_to(&state, &auth_user, &req) {
+        Some(claims) if !state.token_denylist.is_revoked(&claims.jti) => claims,
+        _ => return Json(IntrospectionResponse::default()),
+    };
+
+    Json(IntrospectionResponse {
+        active: true,
+        sub: Some(claims.sub),
+        role: Some(claims.role),
+        token_type: Some(claims.token_type),
+        exp: Some(claims.exp),
+        iat: Some(claims.iat),
+        jti: Some(claims.jti),
+        act: claims.act,
+    })
+}
+
+/// Token revocation handler
+///
+/// POST /api/v1/auth/revoke
+///
+/// Adds the token to the server-side denylist so it is rejected before it
+/// expires. As in RFC 7009, invalid or unknown tokens still return 200 so the
+/// response does not reveal whether a token was valid. Admins may revoke any
+/// user's token; other users only their own.
+///
+/// # Request Body
+///
+/// ```json
+/// {
+///   "token": "jwt_token",
+///   "token_type_hint": "access_token", // Optional
+///   "reason": "Laptop stolen"          // Optional
+/// }
+/// ```
+pub async fn revoke_token_handler(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Json(req): Json<TokenRequest>,
+) -> Result<StatusCode> {
+    let Some(claims) = claims_visible_to(&state, &auth_user, &req) else {
+        return Ok(StatusCode::OK);
+    };
+
+    if state.token_denylist.is_revoked(&claims.jti) {
+        return Ok(StatusCode::OK);
+    }
+
+    let token_user_id = uuid::Uuid::parse_str(&claims.sub)
+        .map_err(|_| crate::utils::AppError::Unauthorized("Invalid user ID in token".to_string()))?;
+
+    state
+        .token_denylist
+        .revoke(
+            &state.pool,
+            RevokedToken {
+                jti: claims.jti.clone(),
+                user_id: token_user_id,
+                token_type: claims.token_type.clone(),
+                expires_at: expiry_from_claim(claims.exp),
+                revoked_by: auth_user.user_id,
+                reason: req.reason.clone(),
+            },
+        )
+        .await?;
+
+    tracing::info!(
+        "{} token {} of user {} revoked by {}",
+        claims.token_type,
+        claims.jti,
+        token_user_id,
+        auth_user.user_id
+    );
+
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(auth_user.user_id),
+            action: AuditAction::Update,
+            entity_type: EntityType::User,
+            entity_id: Some(token_user_id.to_string()),
+            changes: Some(serde_json::json!({
+                "token_revoked": {
+                    "jti": claims.jti,
+                    "token_type": claims.token_type,
+                    "reason": req.reason,
+                }
+            })),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+
+    Ok(StatusCode::OK)
+}
+
+/// JWKS handler
+///
+/// GET /.well-known/jwks.json and GET /api/v1/auth/jwks
+///
+/// Publishes the public keys used to sign access tokens so that other
+/// services can verify them without sharing a secret. Retired keys stay in the
+/// set until removed from the key directory. Returns an empty set when access
+/// tokens are signed with HS256.
+pub async fn jwks_handler(State(state): State<AppState>) -> impl IntoResponse {
+    (
+        [(header::CACHE_CONTROL, "public, max-age=300")],
+        Json(state.auth_service.jwks()),
+    )
+}
+
+/// CAPTCHA configuration response
+#[derive(Debug, Serialize)]
+pub struct CaptchaConfigResponse {
+    pub enabled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub site_key: Option<String>,
+}
+
+/// CAPTCHA configuration handler
+///
+/// GET /api/v1/auth/captcha
+///
+/// Returns the provider and public site key the login form needs to render
+/// the CAPTCHA widget when a login fails with `CAPTCHA_REQUIRED`.
+pub async fn captcha_config_handler(State(state): State<AppState>) -> Json<CaptchaConfigResponse> {
+    let captcha = state.auth_service.captcha();
+    Json(CaptchaConfigResponse {
+        enabled: captcha.is_some(),
+        provider: captcha.map(|c| c.provider().as_str()),
+        site_key: captcha.map(|c| c.site_key().to_string()),
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -247,6 +785,7 @@ mod tests {
             refresh_secret: "test_refresh_secret_key_minimum_32_characters_long".to_string(),
             access_token_expiry: 1800,
             refresh_token_expiry: 604800,
+            key_ring: None,
         };
 
         let security_config = SecurityConfig {
@@ -254,6 +793,7 @@ mod tests {
             session_timeout: 1800,
             max_failed_login_attempts: 5,
             lockout_duration: 900,
+            captcha: None,
         };
 
         AuthService::new(jwt_config, security_config)
@@ -282,9 +822,16 @@ mod tests {
             pool: pool.clone(),
             auth_service: test_auth_service(),
             session_manager: SessionManager::new(1800),
+            token_denylist: TokenDenylist::new(),
+            login_throttle: LoginThrottle::new(),
+            rate_limiter: RateLimitLayer::new(),
+            admin_ip_allowlist: AdminIpAllowlist::default(),
             encryption_key: None, // Not needed for auth test
             email_service: None,  // Not needed for auth test
+            sistema_ts: None,
+            video_room_provider: None,
             settings_service: Arc::new(SettingsService::new(pool)),
+            reference_cache: ReferenceCache::memory(),
             start_time: std::time::SystemTime::now(),
             environment: "test".to_string(),
             #[cfg(feature = "rbac")]
@@ -0,0 +1,392 @@
+//! Authorization Policy Handlers
+//!
+//! Runtime management of Casbin permission rules and role assignments. Changes
+//! apply immediately and are persisted in `casbin_rules`, so they survive
+//! restarts without editing `casbin/policy.csv`.
+//!
+//! Access is checked with `require_admin` rather than Casbin, so a bad policy
+//! change cannot lock administrators out of fixing it.
+//!
+//! `POST /authorization/reload` re-reads `casbin/model.conf` and the stored
+//! rules (e.g. after a migration or a direct edit of `casbin_rules`). The new
+//! policy is validated before it replaces the active one.
+//!
+//! Also serves permission introspection for the current user
+//! (`/auth/permissions`, `/auth/can`), which the frontend uses to decide which
+//! actions to show.
+
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use uuid::Uuid;
+
+use crate::handlers::auth::AppState;
+use crate::middleware::authorization::PolicyReloadError;
+use crate::models::authorization_policy::{
+    EffectivePermissionsResponse, PermissionCheckRequest, PermissionCheckResponse,
+    PolicyListResponse, PolicyReloadResponse, PolicyRule, RoleAssignment,
+};
+use crate::models::user::UserRole;
+use crate::models::{AuditAction, AuditLog, CreateAuditLog, EntityType, RequestContext};
+use crate::utils::permissions::require_admin;
+
+/// Error response body
+fn error_response(error: &str, message: &str) -> Json<serde_json::Value> {
+    Json(serde_json::json!({
+        "error": error,
+        "message": message
+    }))
+}
+
+fn validation_error(message: String) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        StatusCode::BAD_REQUEST,
+        error_response("VALIDATION_ERROR", &message),
+    )
+}
+
+fn policy_store_error(e: casbin::Error) -> (StatusCode, Json<serde_json::Value>) {
+    tracing::error!("Failed to update authorization policy: {}", e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        error_response("INTERNAL_ERROR", "Failed to update authorization policy"),
+    )
+}
+
+/// Record a policy change in the audit log
+async fn audit_policy_change(
+    state: &AppState,
+    user_id: Uuid,
+    action: AuditAction,
+    entity_id: String,
+    changes: serde_json::Value,
+    request_ctx: &RequestContext,
+) {
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(user_id),
+            action,
+            entity_type: EntityType::AuthorizationPolicy,
+            entity_id: Some(entity_id),
+            changes: Some(changes),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+}
+
+/// List permission rules and role assignments (ADMIN only)
+///
+/// GET /api/v1/authorization/policies
+pub async fn list_policies(
+    State(state): State<AppState>,
+    Extension(user_role): Extension<UserRole>,
+) -> Result<Json<PolicyListResponse>, (StatusCode, Json<serde_json::Value>)> {
+    require_admin(&user_role)?;
+
+    let mut policies: Vec<PolicyRule> = state
+        .enforcer
+        .policies()
+        .await
+        .iter()
+        .filter_map(|rule| PolicyRule::from_rule(rule))
+        .collect();
+    policies.sort_by(|a, b| {
+        (&a.role, &a.resource, &a.action).cmp(&(&b.role, &b.resource, &b.action))
+    });
+
+    let mut role_assignments: Vec<RoleAssignment> = state
+        .enforcer
+        .role_assignments()
+        .await
+        .iter()
+        .filter_map(|rule| RoleAssignment::from_rule(rule))
+        .collect();
+    role_assignments.sort_by(|a, b| (&a.member, &a.role).cmp(&(&b.member, &b.role)));
+
+    Ok(Json(PolicyListResponse {
+        policies,
+        role_assignments,
+    }))
+}
+
+/// Add a permission rule (ADMIN only)
+///
+/// POST /api/v1/authorization/policies
+pub async fn add_policy(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(user_role): Extension<UserRole>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Json(rule): Json<PolicyRule>,
+) -> Result<(StatusCode, Json<PolicyRule>), (StatusCode, Json<serde_json::Value>)> {
+    require_admin(&user_role)?;
+    rule.validate().map_err(validation_error)?;
+
+    let added = state
+        .enforcer
+        .add_policy(&rule.role, &rule.resource, &rule.action)
+        .await
+        .map_err(policy_store_error)?;
+    if !added {
+        return Err((
+            StatusCode::CONFLICT,
+            error_response("POLICY_EXISTS", "This permission rule already exists"),
+        ));
+    }
+
+    audit_policy_change(
+        &state,
+        user_id,
+        AuditAction::Create,
+        format!("p:{}:{}:{}", rule.role, rule.resource, rule.action),
+        serde_json::json!({ "policy": rule }),
+        &request_ctx,
+    )
+    .await;
+
+    tracing::warn!(
+        "User {} granted {} on {} to {}",
+        user_id,
+        rule.action,
+        rule.resource,
+        rule.role
+    );
+
+    Ok((StatusCode::CREATED, Json(rule)))
+}
+
+/// Remove a permission rule (ADMIN only)
+///
+/// DELETE /api/v1/authorization/policies
+pub async fn remove_policy(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(user_role): Extension<UserRole>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Json(rule): Json<PolicyRule>,
+) -> Result<StatusCode, (StatusCode, Json<serde_json::Value>)> {
+    require_admin(&user_role)?;
+    rule.validate().map_err(validation_error)?;
+
+    let removed = state
+        .enforcer
+        .remove_policy(&rule.role, &rule.resource, &rule.action)
+        .await
+        .map_err(policy_store_error)?;
+    if !removed {
+        return Err((
+            StatusCode::NOT_FOUND,
+            error_response("POLICY_NOT_FOUND", "Permission rule not found"),
+        ));
+    }
+
+    audit_policy_change(
+        &state,
+        user_id,
+        AuditAction::Delete,
+        format!("p:{}:{}:{}", rule.role, rule.resource, rule.action),
+        serde_json::json!({ "policy": rule }),
+        &request_ctx,
+    )
+    .await;
+
+    tracing::warn!(
+        "User {} revoked {} on {} from {}",
+        user_id,
+        rule.action,
+        rule.resource,
+        rule.role
+    );
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Add a role assignment (ADMIN only)
+///
+/// POST /api/v1/authorization/role-assignments
+pub async fn add_role_assignment(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(user_role): Extension<UserRole>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Json(assignment): Json<RoleAssignment>,
+) -> Result<(StatusCode, Json<RoleAssignment>), (StatusCode, Json<serde_json::Value>)> {
+    require_admin(&user_role)?;
+    assignment.validate().map_err(validation_error)?;
+
+    let added = state
+        .enforcer
+        .add_role_for_user(&assignment.member, &assignment.role)
+        .await
+        .map_err(policy_store_error)?;
+    if !added {
+        return Err((
+            StatusCode::CONFLICT,
+            error_response("POLICY_EXISTS", "This role assignment already exists"),
+        ));
+    }
+
+    audit_policy_change(
+        &state,
+        user_id,
+        AuditAction::Create,
+        format!("g:{}:{}", assignment.member, assignment.role),
+        serde_json::json!({ "role_assignment": assignment }),
+        &request_ctx,
+    )
+    .await;
+
+    tracing::warn!(
+        "User {} assigned role {} to {}",
+        user_id,
+        assignment.role,
+        assignment.member
+    );
+
+    Ok((StatusCode::CREATED, Json(assignment)))
+}
+
+/// Remove a role assignment (ADMIN only)
+///
+/// DELETE /api/v1/authorization/role-assignments
+pub async fn remove_role_assignment(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(user_role): Extension<UserRole>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Json(assignment): Json<RoleAssignment>,
+) -> Result<StatusCode, (StatusCode, Json<serde_json::Value>)> {
+    require_admin(&user_role)?;
+    assignment.validate().map_err(validation_error)?;
+
+    let removed = state
+        .enforcer
+        .remove_role_for_user(&assignment.member, &assignment.role)
+        .await
+        .map_err(policy_store_error)?;
+    if !removed {
+        return Err((
+            StatusCode::NOT_FOUND,
+            error_response("POLICY_NOT_FOUND", "Role assignment not found"),
+        ));
+    }
+
+    audit_policy_change(
+        &state,
+        user_id,
+        AuditAction::Delete,
+        format!("g:{}:{}", assignment.member, assignment.role),
+        serde_json::json!({ "role_assignment": assignment }),
+        &request_ctx,
+    )
+    .await;
+
+    tracing::warn!(
+        "User {} removed role {} from {}",
+        user_id,
+        assignment.role,
+        assignment.member
+    );
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Reload the authorization model and policies (ADMIN only)
+///
+/// POST /api/v1/authorization/reload
+///
+/// Returns 422 if the new policy fails to load or validate; the previous
+/// policy stays active in that case.
+pub async fn reload_policies(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(user_role): Extension<UserRole>,
+    Extension(request_ctx): Extension<RequestContext>,
+) -> Result<Json<PolicyReloadResponse>, (StatusCode, Json<serde_json::Value>)> {
+    require_admin(&user_role)?;
+
+    let summary = state.enforcer.reload().await.map_err(|e| {
+        tracing::error!("Authorization policy reload by {} failed: {}", user_id, e);
+        let error = match e {
+            PolicyReloadError::Load(_) => "POLICY_LOAD_FAILED",
+            PolicyReloadError::Rejected(_) => "POLICY_REJECTED",
+        };
+        (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            error_response(error, &e.to_string()),
+        )
+    })?;
+
+    audit_policy_change(
+        &state,
+        user_id,
+        AuditAction::Update,
+        "reload".to_string(),
+        serde_json::json!({
+            "reload": {
+                "policies": summary.policies,
+                "role_assignments": summary.role_assignments,
+            }
+        }),
+        &request_ctx,
+    )
+    .await;
+
+    tracing::warn!(
+        "User {} reloaded authorization policy ({} rules, {} role assignments)",
+        user_id,
+        summary.policies,
+        summary.role_assignments
+    );
+
+    Ok(Json(PolicyReloadResponse {
+        policies: summary.policies,
+        role_assignments: summary.role_assignments,
+    }))
+}
+
+/// Effective permission matrix of the current user
+///
+/// GET /api/v1/auth/permissions
+pub async fn get_my_permissions(
+    State(state): State<AppState>,
+    Extension(user_role): Extension<UserRole>,
+) -> Json<EffectivePermissionsResponse> {
+    let permissions = state.enforcer.effective_permissions(&user_role).await;
+
+    Json(EffectivePermissionsResponse {
+        role: user_role.to_string(),
+        permissions,
+    })
+}
+
+/// Check whether the current user may perform an action on a resource
+///
+/// POST /api/v1/auth/can
+pub async fn check_my_permission(
+    State(state): State<AppState>,
+    Extension(user_role): Extension<UserRole>,
+    Json(req): Json<PermissionCheckRequest>,
+) -> Result<Json<PermissionCheckResponse>, (StatusCode, Json<serde_json::Value>)> {
+    req.validate().map_err(validation_error)?;
+
+    let allowed = state
+        .enforcer
+        .enforce(&user_role, &req.resource, &req.action)
+        .await
+        .map_err(|e| {
+            tracing::error!("Permission check error: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                error_response("AUTHORIZATION_ERROR", "Failed to check permissions"),
+            )
+        })?;
+
+    Ok(Json(PermissionCheckResponse {
+        resource: req.resource,
+        action: req.action,
+        allowed,
+    }))
+}
@@ -0,0 +1,71 @@
+/*!
+ * Batch Operation Handlers
+ *
+ * Several appointment operations in one request, with one result per
+ * operation (see `models::batch`).
+ *
+ * Endpoints:
+ * - POST /api/v1/batch - Run a batch of operations
+ */
+
+use axum::{extract::State, Extension, Json};
+use std::collections::BTreeSet;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    handlers::{appointments::check_permission, auth::AppState},
+    models::{BatchRequest, BatchResponse, RequestContext, UserRole},
+    services::BatchService,
+    utils::{AppError, Result},
+};
+
+/// Run a batch of operations
+///
+/// POST /api/v1/batch
+///
+/// Returns 200 with one result per operation, in request order, each with
+/// the status and body the equivalent single request would have returned.
+/// The user needs the permissions of every operation in the batch, or
+/// nothing is run (403).
+pub async fn execute_batch(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(user_role): Extension<UserRole>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Json(req): Json<BatchRequest>,
+) -> Result<Json<BatchResponse>> {
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let actions: BTreeSet<&str> = req
+        .operations
+        .iter()
+        .flat_map(|operation| {
+            std::iter::once(operation.permission_action())
+                .chain(operation.overbooks().then_some("overbook"))
+        })
+        .collect();
+    for action in actions {
+        check_permission(&state, &user_role, action).await?;
+    }
+
+    let encryption_key = state
+        .encryption_key
+        .as_ref()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+
+    let response = BatchService::new(state.pool.clone(), encryption_key.clone())
+        .execute(req, user_id, Some(&request_ctx))
+        .await?;
+
+    tracing::info!(
+        "Batch of {} operations by user {}: {} succeeded, {} failed",
+        response.results.len(),
+        user_id,
+        response.succeeded,
+        response.failed
+    );
+
+    Ok(Json(response))
+}
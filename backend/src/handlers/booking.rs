@@ -0,0 +1,276 @@
+/*!
+ * Online Booking Handlers
+ *
+ * The public self-booking API, mounted at `/api/booking/v1` apart from the
+ * staff API. There is no sign-in: slots are named by signed slot tokens,
+ * requests pass a CAPTCHA and are confirmed from a link emailed to the
+ * patient. Every endpoint answers 404 while online booking is off.
+ *
+ * Endpoints:
+ * - GET /api/booking/v1/options - Bookable appointment types and doctors
+ * - GET /api/booking/v1/slots - Free slots for an appointment type
+ * - POST /api/booking/v1/requests - Request a slot (emails a verification link)
+ * - POST /api/booking/v1/requests/verify - Verify a request; books a PENDING appointment
+ */
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use chrono::{Duration, Utc};
+use validator::Validate;
+
+use crate::{
+    handlers::auth::AppState,
+    models::{
+        booking::{
+            BookableAppointmentType, BookingCaptchaConfig, BookingProviderResponse,
+            BookingSettings, BOOKING_VERIFICATION_MINUTES, MAX_BOOKING_SLOT_DAYS,
+        },
+        AuditAction, AuditLog, BookingConfirmationResponse, BookingOptionsResponse,
+        BookingRequestAccepted, BookingSlotsQuery, BookingSlotsResponse, CreateAuditLog,
+        CreateBookingRequest, EntityType, RequestContext, VerifyBookingRequest,
+    },
+    services::BookingService,
+    utils::{AppError, Result},
+};
+
+fn booking_service(state: &AppState) -> Result<BookingService> {
+    let encryption_key = state
+        .encryption_key
+        .as_ref()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+
+    Ok(BookingService::new(
+        state.pool.clone(),
+        encryption_key.clone(),
+    ))
+}
+
+/// Online booking settings; 404 while online booking is off or cannot be
+/// protected by a CAPTCHA
+async fn require_booking_enabled(state: &AppState) -> Result<BookingSettings> {
+    let settings = BookingService::settings(&state.settings_service).await;
+
+    if !settings.enabled || state.auth_service.captcha().is_none() {
+        return Err(AppError::NotFound(
+            "Online booking is not enabled".to_string(),
+        ));
+    }
+    Ok(settings)
+}
+
+/// Bookable appointment types and doctors
+///
+/// GET /api/booking/v1/options
+pub async fn get_booking_options(
+    State(state): State<AppState>,
+) -> Result<Json<BookingOptionsResponse>> {
+    let settings = require_booking_enabled(&state).await?;
+    let providers = booking_service(&state)?.providers(&settings).await?;
+    let captcha = state
+        .auth_service
+        .captcha()
+        .ok_or_else(|| AppError::NotFound("Online booking is not enabled".to_string()))?;
+
+    Ok(Json(BookingOptionsResponse {
+        appointment_types: settings
+            .appointment_types
+            .iter()
+            .map(|t| BookableAppointmentType {
+                appointment_type: *t,
+                duration_minutes: t.default_duration(),
+            })
+            .collect(),
+        providers: providers
+            .iter()
+            .map(BookingProviderResponse::from)
+            .collect(),
+        min_notice_hours: settings.min_notice_hours,
+        max_days_ahead: settings.max_days_ahead,
+        captcha: BookingCaptchaConfig {
+            provider: captcha.provider().as_str(),
+            site_key: captcha.site_key().to_string(),
+        },
+    }))
+}
+
+/// Free slots for an appointment type
+///
+/// GET /api/booking/v1/slots?type=FOLLOW_UP&from=2026-05-04&days=7&provider_id=...
+///
+/// Each slot carries a slot token, valid for an hour, that names it in the
+/// booking request.
+pub async fn list_booking_slots(
+    State(state): State<AppState>,
+    Query(query): Query<BookingSlotsQuery>,
+) -> Result<Json<BookingSlotsResponse>> {
+    let settings = require_booking_enabled(&state).await?;
+
+    if !settings.allows_type(query.appointment_type) {
+        return Err(AppError::BadRequest(
+            "This appointment type cannot be booked online".to_string(),
+        ));
+    }
+
+    let today = Utc::now().date_naive();
+    let from = query.from.unwrap_or(today).max(today);
+    let days = query
+        .days
+        .unwrap_or(MAX_BOOKING_SLOT_DAYS)
+        .clamp(1, MAX_BOOKING_SLOT_DAYS);
+    let to = from + Duration::days(days - 1);
+
+    let service = booking_service(&state)?;
+    let providers: Vec<_> = service
+        .providers(&settings)
+        .await?
+        .into_iter()
+        .filter(|p| query.provider_id.map_or(true, |id| p.id == id))
+        .collect();
+
+    let slots = service
+        .available_slots(
+            &state.auth_service,
+            &settings,
+            &providers,
+            query.appointment_type,
+            from,
+            days,
+        )
+        .await?;
+
+    Ok(Json(BookingSlotsResponse {
+        appointment_type: query.appointment_type,
+        duration_minutes: query.appointment_type.default_duration(),
+        from,
+        to,
+        slots,
+    }))
+}
+
+/// Request a slot
+///
+/// POST /api/booking/v1/requests
+///
+/// Needs a CAPTCHA token. Answers 202 with the same message whether or not
+/// the request was stored (an email address may make a few requests an
+/// hour); the slot is only booked once the emailed link is opened.
+pub async fn create_booking_request(
+    State(state): State<AppState>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Json(req): Json<CreateBookingRequest>,
+) -> Result<(StatusCode, Json<BookingRequestAccepted>)> {
+    let settings = require_booking_enabled(&state).await?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    if let Some(captcha) = state.auth_service.captcha() {
+        let token = req
+            .captcha_token
+            .as_deref()
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .ok_or_else(|| {
+                AppError::CaptchaRequired("CAPTCHA verification is required".to_string())
+            })?;
+
+        if !captcha
+            .verify(token, request_ctx.ip_address.as_deref())
+            .await
+        {
+            return Err(AppError::CaptchaRequired(
+                "CAPTCHA verification failed".to_string(),
+            ));
+        }
+    }
+
+    let claims = state
+        .auth_service
+        .validate_booking_slot_token(&req.slot_token)?;
+    let service = booking_service(&state)?;
+    let slot = service.offered_slot(&settings, &claims).await?;
+
+    service
+        .create_request(
+            state.email_service.as_ref(),
+            &slot,
+            &req,
+            request_ctx.ip_address.as_deref(),
+        )
+        .await?;
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(BookingRequestAccepted {
+            message: "Please open the link sent to your email address to complete the booking"
+                .to_string(),
+            expires_in_minutes: BOOKING_VERIFICATION_MINUTES,
+        }),
+    ))
+}
+
+/// Verify a booking request
+///
+/// POST /api/booking/v1/requests/verify
+///
+/// Books the slot as a PENDING appointment, creating the patient record if
+/// the details match none. Answers 409 if the slot was taken in the
+/// meantime.
+pub async fn verify_booking_request(
+    State(state): State<AppState>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Json(req): Json<VerifyBookingRequest>,
+) -> Result<Json<BookingConfirmationResponse>> {
+    require_booking_enabled(&state).await?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let (confirmation, outcome) = booking_service(&state)?.verify_request(&req.token).await?;
+
+    // No staff user: the audit entries name the booking request instead
+    if outcome.new_patient {
+        let _ = AuditLog::create(
+            &state.pool,
+            CreateAuditLog {
+                user_id: None,
+                action: AuditAction::Create,
+                entity_type: EntityType::Patient,
+                entity_id: Some(outcome.patient_id.to_string()),
+                changes: Some(serde_json::json!({
+                    "source": "online_booking",
+                    "booking_request_id": outcome.booking_request_id,
+                })),
+                ip_address: request_ctx.ip_address.clone(),
+                user_agent: request_ctx.user_agent.clone(),
+                request_id: Some(request_ctx.request_id),
+            },
+        )
+        .await;
+    }
+
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: None,
+            action: AuditAction::Create,
+            entity_type: EntityType::Appointment,
+            entity_id: Some(outcome.appointment_id.to_string()),
+            changes: Some(serde_json::json!({
+                "source": "online_booking",
+                "booking_request_id": outcome.booking_request_id,
+                "patient_id": outcome.patient_id,
+                "status": confirmation.status,
+            })),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+
+    Ok(Json(confirmation))
+}
@@ -0,0 +1,392 @@
+/*!
+ * Branding HTTP Handlers
+ *
+ * HTTP request handlers for white-label practice theming.
+ * Metadata and asset images are public (needed before login);
+ * modifications require ADMIN role.
+ */
+
+use axum::{
+    body::Body,
+    extract::{Multipart, Path, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use uuid::Uuid;
+
+use crate::handlers::auth::AppState;
+use crate::models::branding::{
+    BrandingAsset, BrandingResponse, UpdateBrandingRequest, DOCUMENT_ACCENT_COLOR_KEY,
+    PRIMARY_COLOR_KEY, PUBLIC_BASE_URL_KEY,
+};
+use crate::models::system_setting::{BulkUpdateSettingsRequest, SettingUpdate};
+use crate::models::uploaded_file::{UploadedFileResponse, MAX_FILE_SIZE};
+use crate::models::{AuditAction, AuditLog, CreateAuditLog, EntityType, RequestContext, UserRole};
+use crate::services::BrandingService;
+
+#[cfg(feature = "rbac")]
+use crate::utils::permissions::require_admin;
+
+/// Error response helper
+fn error_response(error: &str, message: &str) -> Json<serde_json::Value> {
+    Json(serde_json::json!({
+        "error": error,
+        "message": message
+    }))
+}
+
+/// Parse the asset path segment or return 404
+fn parse_asset(asset: &str) -> Result<BrandingAsset, (StatusCode, Json<serde_json::Value>)> {
+    BrandingAsset::from_path(asset).ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            error_response("NOT_FOUND", &format!("Unknown branding asset: {}", asset)),
+        )
+    })
+}
+
+/// Get public branding metadata
+///
+/// GET /api/v1/branding
+///
+/// No authentication required (used to theme the login page).
+///
+/// Returns: BrandingResponse with colors and logo/email header URLs
+pub async fn get_branding(
+    State(state): State<AppState>,
+) -> Result<Json<BrandingResponse>, (StatusCode, Json<serde_json::Value>)> {
+    let service = BrandingService::new(state.pool.clone());
+
+    let branding = service.get_branding().await.map_err(|e| {
+        tracing::error!("Failed to load branding: {}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            error_response("INTERNAL_ERROR", "Failed to retrieve branding"),
+        )
+    })?;
+
+    Ok(Json(branding))
+}
+
+/// Update branding colors and public base URL
+///
+/// PUT /api/v1/branding
+///
+/// Body: UpdateBrandingRequest (all fields optional)
+///
+/// Returns: Updated BrandingResponse
+pub async fn update_branding(
+    State(state): State<AppState>,
+    Extension(user_role): Extension<UserRole>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Json(request): Json<UpdateBrandingRequest>,
+) -> Result<Json<BrandingResponse>, (StatusCode, Json<serde_json::Value>)> {
+    // Only admins can change branding
+    #[cfg(feature = "rbac")]
+    require_admin(&user_role)?;
+
+    use validator::Validate;
+    request.validate().map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            error_response("VALIDATION_ERROR", &e.to_string()),
+        )
+    })?;
+
+    let mut updates = Vec::new();
+    if let Some(color) = request.primary_color {
+        updates.push(SettingUpdate {
+            key: PRIMARY_COLOR_KEY.to_string(),
+            value: serde_json::Value::String(color.to_lowercase()),
+        });
+    }
+    if let Some(color) = request.document_accent_color {
+        updates.push(SettingUpdate {
+            key: DOCUMENT_ACCENT_COLOR_KEY.to_string(),
+            value: serde_json::Value::String(color.to_lowercase()),
+        });
+    }
+    if let Some(url) = request.public_base_url {
+        updates.push(SettingUpdate {
+            key: PUBLIC_BASE_URL_KEY.to_string(),
+            value: serde_json::Value::String(url),
+        });
+    }
+
+    if !updates.is_empty() {
+        state
+            .settings_service
+            .bulk_update_settings(
+                BulkUpdateSettingsRequest { settings: updates },
+                user_id,
+                Some(&request_ctx),
+            )
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to update branding settings: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    error_response("INTERNAL_ERROR", "Failed to update branding"),
+                )
+            })?;
+    }
+
+    get_branding(State(state)).await
+}
+
+/// Serve a branding image asset
+///
+/// GET /api/v1/branding/assets/:asset
+///
+/// Path parameters:
+/// - asset: logo-light, logo-dark, logo-compact, or email-header
+///
+/// No authentication required (embedded in emails and the login page).
+///
+/// Returns: Image content or 404
+pub async fn serve_branding_asset(
+    State(state): State<AppState>,
+    Path(asset): Path<String>,
+) -> Result<Response, (StatusCode, Json<serde_json::Value>)> {
+    let asset = parse_asset(&asset)?;
+    let service = BrandingService::new(state.pool.clone());
+
+    let (file, content) = service
+        .get_asset(asset)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get branding asset {}: {}", asset, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                error_response("INTERNAL_ERROR", "Failed to retrieve branding asset"),
+            )
+        })?
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                error_response("NOT_FOUND", "Branding asset has not been set"),
+            )
+        })?;
+
+    let mut headers = HeaderMap::new();
+
+    // Content-Type
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_str(&file.mime_type).unwrap_or(HeaderValue::from_static("image/png")),
+    );
+
+    // Content-Length
+    headers.insert(
+        header::CONTENT_LENGTH,
+        HeaderValue::from_str(&content.len().to_string()).unwrap(),
+    );
+
+    // Inline disposition
+    headers.insert(
+        header::CONTENT_DISPOSITION,
+        HeaderValue::from_static("inline"),
+    );
+
+    // Cache control - assets change rarely, but keep it short enough that
+    // a replaced logo shows up the same day
+    headers.insert(
+        header::CACHE_CONTROL,
+        HeaderValue::from_static("public, max-age=3600"),
+    );
+
+    // Security headers
+    headers.insert(
+        header::X_CONTENT_TYPE_OPTIONS,
+        HeaderValue::from_static("nosniff"),
+    );
+
+    Ok((StatusCode::OK, headers, Body::from(content)).into_response())
+}
+
+/// Upload a branding image asset
+///
+/// POST /api/v1/branding/assets/:asset
+///
+/// Accepts multipart form data with:
+/// - file: The image (required, JPEG/PNG/SVG)
+///
+/// Replaces any existing image in the same slot.
+/// Returns: UploadedFileResponse with file metadata
+pub async fn upload_branding_asset(
+    State(state): State<AppState>,
+    Extension(user_role): Extension<UserRole>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(asset): Path<String>,
+    mut multipart: Multipart,
+) -> Result<Json<UploadedFileResponse>, (StatusCode, Json<serde_json::Value>)> {
+    // Only admins can upload branding assets
+    #[cfg(feature = "rbac")]
+    require_admin(&user_role)?;
+
+    let asset = parse_asset(&asset)?;
+
+    let mut file_content: Option<Vec<u8>> = None;
+    let mut original_filename: Option<String> = None;
+
+    // Parse multipart form
+    while let Some(field) = multipart.next_field().await.map_err(|e| {
+        tracing::error!("Failed to parse multipart field: {}", e);
+        (
+            StatusCode::BAD_REQUEST,
+            error_response("INVALID_MULTIPART", "Failed to parse multipart form"),
+        )
+    })? {
+        let name = field.name().unwrap_or("").to_string();
+
+        if name == "file" {
+            original_filename = field.file_name().map(|s| s.to_string());
+
+            let content = field.bytes().await.map_err(|e| {
+                tracing::error!("Failed to read branding asset content: {}", e);
+                (
+                    StatusCode::BAD_REQUEST,
+                    error_response("READ_ERROR", "Failed to read file content"),
+                )
+            })?;
+
+            if content.len() > MAX_FILE_SIZE {
+                return Err((
+                    StatusCode::PAYLOAD_TOO_LARGE,
+                    error_response("FILE_TOO_LARGE", "Branding asset file is too large"),
+                ));
+            }
+
+            file_content = Some(content.to_vec());
+        }
+    }
+
+    let content = file_content.ok_or_else(|| {
+        (
+            StatusCode::BAD_REQUEST,
+            error_response("MISSING_FILE", "No file was provided"),
+        )
+    })?;
+
+    let filename = original_filename.unwrap_or_else(|| asset.as_path().to_string());
+
+    let service = BrandingService::new(state.pool.clone());
+    let file = service
+        .upload_asset(
+            &state.settings_service,
+            asset,
+            &content,
+            &filename,
+            user_id,
+            Some(&request_ctx),
+        )
+        .await
+        .map_err(|e| {
+            let error_msg = e.to_string();
+            tracing::error!("Branding asset upload failed: {}", error_msg);
+
+            if error_msg.contains("validation failed") || error_msg.contains("not allowed") {
+                (
+                    StatusCode::BAD_REQUEST,
+                    error_response("VALIDATION_FAILED", &error_msg),
+                )
+            } else if error_msg.contains("security") || error_msg.contains("SVG") {
+                (
+                    StatusCode::BAD_REQUEST,
+                    error_response("SECURITY_VIOLATION", &error_msg),
+                )
+            } else {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    error_response("UPLOAD_FAILED", "Failed to upload branding asset"),
+                )
+            }
+        })?;
+
+    // Create audit log for branding asset upload
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(user_id),
+            action: AuditAction::Create,
+            entity_type: EntityType::File,
+            entity_id: Some(file.id.to_string()),
+            changes: Some(serde_json::json!({
+                "original_filename": file.original_filename,
+                "mime_type": file.mime_type,
+                "purpose": "BRANDING",
+                "branding_asset": asset.as_path(),
+                "size_bytes": file.file_size_bytes,
+            })),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+
+    Ok(Json(UploadedFileResponse::from(file)))
+}
+
+/// Remove a branding image asset
+///
+/// DELETE /api/v1/branding/assets/:asset
+///
+/// Returns: 204 No Content on success
+pub async fn delete_branding_asset(
+    State(state): State<AppState>,
+    Extension(user_role): Extension<UserRole>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(asset): Path<String>,
+) -> Result<StatusCode, (StatusCode, Json<serde_json::Value>)> {
+    // Only admins can remove branding assets
+    #[cfg(feature = "rbac")]
+    require_admin(&user_role)?;
+
+    let asset = parse_asset(&asset)?;
+
+    let service = BrandingService::new(state.pool.clone());
+    let file_id = service
+        .remove_asset(&state.settings_service, asset, user_id, Some(&request_ctx))
+        .await
+        .map_err(|e| {
+            let error_msg = e.to_string();
+            if error_msg.contains("not found") {
+                (
+                    StatusCode::NOT_FOUND,
+                    error_response("NOT_FOUND", "Branding asset has not been set"),
+                )
+            } else {
+                tracing::error!("Failed to remove branding asset {}: {}", asset, e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    error_response("INTERNAL_ERROR", "Failed to remove branding asset"),
+                )
+            }
+        })?;
+
+    // Create audit log for branding asset removal
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(user_id),
+            action: AuditAction::Delete,
+            entity_type: EntityType::File,
+            entity_id: Some(file_id.to_string()),
+            changes: Some(serde_json::json!({
+                "purpose": "BRANDING",
+                "branding_asset": asset.as_path(),
+            })),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
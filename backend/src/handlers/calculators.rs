@@ -0,0 +1,271 @@
+/*!
+ * Clinical Calculator Handlers
+ *
+ * Clinical scores from structured inputs, and results saved into a visit.
+ *
+ * Endpoints:
+ * - POST /api/v1/calculators/bmi - Body mass index
+ * - POST /api/v1/calculators/egfr - eGFR (CKD-EPI 2021)
+ * - POST /api/v1/calculators/cha2ds2-vasc - CHA₂DS₂-VASc stroke risk
+ * - POST /api/v1/calculators/has-bled - HAS-BLED bleeding risk
+ * - POST /api/v1/calculators/score2 - SCORE2 10-year cardiovascular risk
+ * - GET /api/v1/visits/:id/calculations - Calculations saved into a visit
+ * - POST /api/v1/visits/:id/calculations - Compute and save into a draft visit
+ * - DELETE /api/v1/visits/:id/calculations/:calculation_id - Delete a saved calculation
+ */
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use uuid::Uuid;
+
+use crate::{
+    handlers::auth::AppState,
+    models::{
+        AuditAction, AuditLog, AuthUser, CreateAuditLog, EntityType, RequestContext, UserRole,
+        VisitCalculationResponse,
+    },
+    services::{
+        clinical_calculators::{
+            calculate_bmi, calculate_cha2ds2_vasc, calculate_egfr, calculate_has_bled,
+            calculate_score2, BmiInput, CalculatorInput, CalculatorResult, Cha2ds2VascInput,
+            EgfrInput, HasBledInput, Score2Input,
+        },
+        VisitCalculationService,
+    },
+    utils::{AppError, Result},
+};
+
+#[cfg(feature = "rbac")]
+use tracing::warn;
+
+/// Check if user has permission to perform action on calculators resource
+#[cfg(feature = "rbac")]
+async fn check_permission(state: &AppState, user_role: &UserRole, action: &str) -> Result<()> {
+    let has_permission = state
+        .enforcer
+        .enforce(user_role, "calculators", action)
+        .await
+        .map_err(|e| {
+            warn!("RBAC enforcement error: {}", e);
+            AppError::Internal("Failed to check permissions".to_string())
+        })?;
+
+    if !has_permission {
+        return Err(AppError::Forbidden(format!(
+            "User does not have permission to {} calculators",
+            action
+        )));
+    }
+
+    Ok(())
+}
+
+/// Fallback for non-RBAC builds - only checks role
+#[cfg(not(feature = "rbac"))]
+async fn check_permission(_state: &AppState, user_role: &UserRole, action: &str) -> Result<()> {
+    let allowed = match action {
+        "delete" => matches!(user_role, UserRole::Admin | UserRole::Doctor),
+        _ => matches!(
+            user_role,
+            UserRole::Admin | UserRole::Doctor | UserRole::Nurse
+        ),
+    };
+
+    if !allowed {
+        return Err(AppError::Forbidden("Insufficient permissions".to_string()));
+    }
+    Ok(())
+}
+
+fn calculation_service(state: &AppState) -> Result<VisitCalculationService> {
+    let encryption_key = state
+        .encryption_key
+        .clone()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+    Ok(VisitCalculationService::new(
+        state.pool.clone(),
+        encryption_key,
+    ))
+}
+
+/// Audit a saved calculation; the payload never contains inputs or scores
+async fn audit_calculation(
+    state: &AppState,
+    auth_user: &AuthUser,
+    request_ctx: &RequestContext,
+    action: AuditAction,
+    calculation_id: Uuid,
+    changes: serde_json::Value,
+) {
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(auth_user.user_id),
+            action,
+            entity_type: EntityType::VisitCalculation,
+            entity_id: Some(calculation_id.to_string()),
+            changes: Some(changes),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+}
+
+/// Body mass index
+///
+/// POST /api/v1/calculators/bmi
+///
+/// **RBAC**: Requires 'calculate' permission on 'calculators' resource
+pub async fn calculate_bmi_score(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Json(input): Json<BmiInput>,
+) -> Result<Json<CalculatorResult>> {
+    check_permission(&state, &auth_user.role, "calculate").await?;
+    Ok(Json(calculate_bmi(&input)?))
+}
+
+/// Estimated GFR (CKD-EPI 2021)
+///
+/// POST /api/v1/calculators/egfr
+///
+/// **RBAC**: Requires 'calculate' permission on 'calculators' resource
+pub async fn calculate_egfr_score(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Json(input): Json<EgfrInput>,
+) -> Result<Json<CalculatorResult>> {
+    check_permission(&state, &auth_user.role, "calculate").await?;
+    Ok(Json(calculate_egfr(&input)?))
+}
+
+/// CHA₂DS₂-VASc stroke risk
+///
+/// POST /api/v1/calculators/cha2ds2-vasc
+///
+/// **RBAC**: Requires 'calculate' permission on 'calculators' resource
+pub async fn calculate_cha2ds2_vasc_score(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Json(input): Json<Cha2ds2VascInput>,
+) -> Result<Json<CalculatorResult>> {
+    check_permission(&state, &auth_user.role, "calculate").await?;
+    Ok(Json(calculate_cha2ds2_vasc(&input)?))
+}
+
+/// HAS-BLED bleeding risk
+///
+/// POST /api/v1/calculators/has-bled
+///
+/// **RBAC**: Requires 'calculate' permission on 'calculators' resource
+pub async fn calculate_has_bled_score(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Json(input): Json<HasBledInput>,
+) -> Result<Json<CalculatorResult>> {
+    check_permission(&state, &auth_user.role, "calculate").await?;
+    Ok(Json(calculate_has_bled(&input)?))
+}
+
+/// SCORE2 10-year cardiovascular risk
+///
+/// POST /api/v1/calculators/score2
+///
+/// **RBAC**: Requires 'calculate' permission on 'calculators' resource
+pub async fn calculate_score2_risk(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Json(input): Json<Score2Input>,
+) -> Result<Json<CalculatorResult>> {
+    check_permission(&state, &auth_user.role, "calculate").await?;
+    Ok(Json(calculate_score2(&input)?))
+}
+
+/// Calculations saved into a visit
+///
+/// GET /api/v1/visits/:id/calculations
+///
+/// **RBAC**: Requires 'read' permission on 'calculators' resource
+pub async fn list_visit_calculations(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(visit_id): Path<Uuid>,
+) -> Result<Json<Vec<VisitCalculationResponse>>> {
+    check_permission(&state, &auth_user.role, "read").await?;
+
+    let calculations = calculation_service(&state)?
+        .list_calculations(visit_id, auth_user.user_id)
+        .await?;
+
+    Ok(Json(calculations))
+}
+
+/// Compute a calculator and save the result into a draft visit
+///
+/// POST /api/v1/visits/:id/calculations
+///
+/// **RBAC**: Requires 'save' permission on 'calculators' resource
+pub async fn save_visit_calculation(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(visit_id): Path<Uuid>,
+    Json(input): Json<CalculatorInput>,
+) -> Result<(StatusCode, Json<VisitCalculationResponse>)> {
+    check_permission(&state, &auth_user.role, "save").await?;
+
+    let calculation = calculation_service(&state)?
+        .save_calculation(visit_id, &input, auth_user.user_id)
+        .await?;
+
+    audit_calculation(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Create,
+        calculation.id,
+        serde_json::json!({
+            "visit_id": visit_id,
+            "patient_id": calculation.patient_id,
+            "calculator": calculation.calculator,
+        }),
+    )
+    .await;
+
+    Ok((StatusCode::CREATED, Json(calculation)))
+}
+
+/// Delete a calculation from a draft visit
+///
+/// DELETE /api/v1/visits/:id/calculations/:calculation_id
+///
+/// **RBAC**: Requires 'delete' permission on 'calculators' resource
+pub async fn delete_visit_calculation(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path((visit_id, calculation_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode> {
+    check_permission(&state, &auth_user.role, "delete").await?;
+
+    calculation_service(&state)?
+        .delete_calculation(visit_id, calculation_id, auth_user.user_id)
+        .await?;
+
+    audit_calculation(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Delete,
+        calculation_id,
+        serde_json::json!({ "visit_id": visit_id }),
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,206 @@
+/*!
+ * Calendar Feed Handlers
+ *
+ * Read-only iCalendar feed of the signed-in provider's appointments, for
+ * phone and desktop calendars. The feed URL is authenticated by the token
+ * it carries, which is shown once when the feed is created.
+ *
+ * Endpoints:
+ * - GET /api/v1/appointments/calendar-feed - The user's feed settings
+ * - POST /api/v1/appointments/calendar-feed - Create the feed (new URL)
+ * - PUT /api/v1/appointments/calendar-feed - Change its privacy mode
+ * - DELETE /api/v1/appointments/calendar-feed - Revoke the feed
+ * - GET /api/v1/calendar/:token.ics - The feed itself (no JWT)
+ */
+
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Extension, Json,
+};
+use uuid::Uuid;
+
+use crate::{
+    handlers::{appointments::check_permission, auth::AppState},
+    models::{
+        AuditAction, AuditLog, CalendarFeed, CalendarFeedRequest, CreateAuditLog,
+        CreatedCalendarFeed, EntityType, RequestContext, UserRole,
+    },
+    services::CalendarFeedService,
+    utils::{AppError, Result},
+};
+
+fn service(state: &AppState) -> Result<CalendarFeedService> {
+    let encryption_key = state
+        .encryption_key
+        .as_ref()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+    Ok(CalendarFeedService::new(
+        state.pool.clone(),
+        encryption_key.clone(),
+    ))
+}
+
+/// Audit a change to the user's calendar feed
+async fn audit_feed(
+    state: &AppState,
+    user_id: Uuid,
+    request_ctx: &RequestContext,
+    changes: serde_json::Value,
+) {
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(user_id),
+            action: AuditAction::Update,
+            entity_type: EntityType::User,
+            entity_id: Some(user_id.to_string()),
+            changes: Some(changes),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+}
+
+/// The signed-in user's calendar feed
+///
+/// GET /api/v1/appointments/calendar-feed
+///
+/// **RBAC**: Requires 'read' permission on 'appointments' resource
+pub async fn get_calendar_feed_settings(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(user_role): Extension<UserRole>,
+) -> Result<Json<CalendarFeed>> {
+    check_permission(&state, &user_role, "read").await?;
+
+    let feed = service(&state)?
+        .get_feed(user_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Calendar feed not found".to_string()))?;
+
+    Ok(Json(feed))
+}
+
+/// Create the signed-in user's calendar feed
+///
+/// POST /api/v1/appointments/calendar-feed
+///
+/// Returns the feed URL path with its token, which is not shown again.
+/// Creating the feed again replaces the token: the old URL stops working.
+///
+/// **RBAC**: Requires 'read' permission on 'appointments' resource
+pub async fn create_calendar_feed(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(user_role): Extension<UserRole>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Json(request): Json<CalendarFeedRequest>,
+) -> Result<(StatusCode, Json<CreatedCalendarFeed>)> {
+    check_permission(&state, &user_role, "read").await?;
+
+    let created = service(&state)?
+        .create_feed(user_id, request.privacy_mode)
+        .await?;
+
+    audit_feed(
+        &state,
+        user_id,
+        &request_ctx,
+        serde_json::json!({
+            "action": "calendar_feed_created",
+            "privacy_mode": created.feed.privacy_mode,
+        }),
+    )
+    .await;
+
+    Ok((StatusCode::CREATED, Json(created)))
+}
+
+/// Change what the signed-in user's calendar feed shows
+///
+/// PUT /api/v1/appointments/calendar-feed
+///
+/// **RBAC**: Requires 'read' permission on 'appointments' resource
+pub async fn update_calendar_feed(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(user_role): Extension<UserRole>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Json(request): Json<CalendarFeedRequest>,
+) -> Result<Json<CalendarFeed>> {
+    check_permission(&state, &user_role, "read").await?;
+
+    let feed = service(&state)?
+        .set_privacy_mode(user_id, request.privacy_mode)
+        .await?;
+
+    audit_feed(
+        &state,
+        user_id,
+        &request_ctx,
+        serde_json::json!({
+            "action": "calendar_feed_updated",
+            "privacy_mode": feed.privacy_mode,
+        }),
+    )
+    .await;
+
+    Ok(Json(feed))
+}
+
+/// Revoke the signed-in user's calendar feed
+///
+/// DELETE /api/v1/appointments/calendar-feed
+///
+/// **RBAC**: Requires 'read' permission on 'appointments' resource
+pub async fn delete_calendar_feed(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(user_role): Extension<UserRole>,
+    Extension(request_ctx): Extension<RequestContext>,
+) -> Result<StatusCode> {
+    check_permission(&state, &user_role, "read").await?;
+
+    service(&state)?.delete_feed(user_id).await?;
+
+    audit_feed(
+        &state,
+        user_id,
+        &request_ctx,
+        serde_json::json!({ "action": "calendar_feed_revoked" }),
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// The calendar feed with the given token
+///
+/// GET /api/v1/calendar/:token.ics
+///
+/// Public: calendar apps cannot send a JWT, so the token is the credential.
+/// Unknown or revoked tokens are 404.
+pub async fn get_calendar_feed(
+    State(state): State<AppState>,
+    Path(file_name): Path<String>,
+) -> Result<impl IntoResponse> {
+    let not_found = || AppError::NotFound("Calendar feed not found".to_string());
+
+    let token = file_name.strip_suffix(".ics").ok_or_else(not_found)?;
+    let calendar = service(&state)?
+        .render_feed(token)
+        .await?
+        .ok_or_else(not_found)?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/calendar; charset=utf-8"),
+            (header::CACHE_CONTROL, "private, no-store"),
+        ],
+        calendar,
+    ))
+}
@@ -0,0 +1,329 @@
+/*!
+ * Patient Consent Handlers
+ *
+ * Versioned consent texts and patient consents.
+ *
+ * Endpoints:
+ * - GET /api/v1/consent-texts - List consent texts
+ * - POST /api/v1/consent-texts - Publish a new text version
+ * - GET /api/v1/consent-texts/:id - Get a consent text
+ * - GET /api/v1/patients/:id/consents - List a patient's consents
+ * - POST /api/v1/patients/:id/consents - Record a consent
+ * - GET /api/v1/patients/:id/consents/:consent_id - Get a consent with its signature
+ * - POST /api/v1/patients/:id/consents/:consent_id/withdraw - Withdraw a consent
+ */
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    handlers::auth::AppState,
+    models::{
+        AuditAction, AuditLog, AuthUser, ConsentTextResponse, CreateAuditLog,
+        CreateConsentTextRequest, EntityType, ListConsentTextsQuery, ListPatientConsentsQuery,
+        PatientConsentDetailResponse, PatientConsentResponse, RecordConsentRequest, RequestContext,
+        UserRole, WithdrawConsentRequest,
+    },
+    services::PatientConsentService,
+    utils::{AppError, Result},
+};
+
+#[cfg(feature = "rbac")]
+use tracing::warn;
+
+/// Check if user has permission to perform action on consents resource
+#[cfg(feature = "rbac")]
+async fn check_permission(state: &AppState, user_role: &UserRole, action: &str) -> Result<()> {
+    let has_permission = state
+        .enforcer
+        .enforce(user_role, "consents", action)
+        .await
+        .map_err(|e| {
+            warn!("RBAC enforcement error: {}", e);
+            AppError::Internal("Failed to check permissions".to_string())
+        })?;
+
+    if !has_permission {
+        return Err(AppError::Forbidden(format!(
+            "User does not have permission to {} consents",
+            action
+        )));
+    }
+
+    Ok(())
+}
+
+/// Fallback for non-RBAC builds - only checks role
+#[cfg(not(feature = "rbac"))]
+async fn check_permission(_state: &AppState, user_role: &UserRole, action: &str) -> Result<()> {
+    let allowed = match action {
+        "manage_texts" => matches!(user_role, UserRole::Admin),
+        "read" => true,
+        _ => !matches!(user_role, UserRole::Nurse),
+    };
+
+    if !allowed {
+        return Err(AppError::Forbidden("Insufficient permissions".to_string()));
+    }
+    Ok(())
+}
+
+fn consent_service(state: &AppState) -> Result<PatientConsentService> {
+    let encryption_key = state
+        .encryption_key
+        .as_ref()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+
+    Ok(PatientConsentService::new(
+        state.pool.clone(),
+        encryption_key.clone(),
+    ))
+}
+
+async fn audit_consent(
+    state: &AppState,
+    auth_user: &AuthUser,
+    request_ctx: &RequestContext,
+    action: AuditAction,
+    entity_id: Uuid,
+    changes: serde_json::Value,
+) {
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(auth_user.user_id),
+            action,
+            entity_type: EntityType::Consent,
+            entity_id: Some(entity_id.to_string()),
+            changes: Some(changes),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+}
+
+// ============================================================================
+// CONSENT TEXTS
+// ============================================================================
+
+/// List consent texts
+///
+/// GET /api/v1/consent-texts
+///
+/// **RBAC**: Requires 'read' permission on 'consents' resource
+pub async fn list_consent_texts(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Query(query): Query<ListConsentTextsQuery>,
+) -> Result<Json<Vec<ConsentTextResponse>>> {
+    check_permission(&state, &auth_user.role, "read").await?;
+
+    let texts = consent_service(&state)?.list_texts(&query).await?;
+
+    Ok(Json(texts))
+}
+
+/// Get a consent text
+///
+/// GET /api/v1/consent-texts/:id
+///
+/// **RBAC**: Requires 'read' permission on 'consents' resource
+pub async fn get_consent_text(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(text_id): Path<Uuid>,
+) -> Result<Json<ConsentTextResponse>> {
+    check_permission(&state, &auth_user.role, "read").await?;
+
+    let text = consent_service(&state)?.get_text(text_id).await?;
+
+    Ok(Json(text))
+}
+
+/// Publish a new consent text version
+///
+/// POST /api/v1/consent-texts
+///
+/// **RBAC**: Requires 'manage_texts' permission on 'consents' resource
+/// **Roles**: ADMIN
+pub async fn create_consent_text(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Json(req): Json<CreateConsentTextRequest>,
+) -> Result<impl IntoResponse> {
+    check_permission(&state, &auth_user.role, "manage_texts").await?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let text = consent_service(&state)?
+        .create_text(&req, auth_user.user_id)
+        .await?;
+
+    audit_consent(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Create,
+        text.id,
+        serde_json::json!({
+            "consent_text": true,
+            "consent_type": text.consent_type,
+            "version": text.version,
+            "validity_days": text.validity_days,
+        }),
+    )
+    .await;
+
+    Ok((StatusCode::CREATED, Json(text)))
+}
+
+// ============================================================================
+// PATIENT CONSENTS
+// ============================================================================
+
+/// List a patient's consents
+///
+/// GET /api/v1/patients/:id/consents
+///
+/// **RBAC**: Requires 'read' permission on 'consents' resource
+pub async fn list_patient_consents(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(patient_id): Path<Uuid>,
+    Query(query): Query<ListPatientConsentsQuery>,
+) -> Result<Json<Vec<PatientConsentResponse>>> {
+    check_permission(&state, &auth_user.role, "read").await?;
+
+    let consents = consent_service(&state)?
+        .list_patient_consents(patient_id, &query, auth_user.user_id)
+        .await?;
+
+    Ok(Json(consents))
+}
+
+/// Get a patient consent with its text and signature
+///
+/// GET /api/v1/patients/:id/consents/:consent_id
+///
+/// **RBAC**: Requires 'read' permission on 'consents' resource
+pub async fn get_patient_consent(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path((patient_id, consent_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<PatientConsentDetailResponse>> {
+    check_permission(&state, &auth_user.role, "read").await?;
+
+    let consent = consent_service(&state)?
+        .get_patient_consent(patient_id, consent_id, auth_user.user_id)
+        .await?;
+
+    audit_consent(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Read,
+        consent_id,
+        serde_json::json!({ "patient_id": patient_id }),
+    )
+    .await;
+
+    Ok(Json(consent))
+}
+
+/// Record a patient consent
+///
+/// POST /api/v1/patients/:id/consents
+///
+/// Supersedes the patient's current consent of the same type.
+///
+/// **RBAC**: Requires 'create' permission on 'consents' resource
+/// **Roles**: ADMIN, DOCTOR, RECEPTIONIST
+pub async fn record_patient_consent(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(patient_id): Path<Uuid>,
+    Json(req): Json<RecordConsentRequest>,
+) -> Result<impl IntoResponse> {
+    check_permission(&state, &auth_user.role, "create").await?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let consent = consent_service(&state)?
+        .record_consent(patient_id, &req, auth_user.user_id)
+        .await?;
+
+    audit_consent(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Create,
+        consent.id,
+        serde_json::json!({
+            "patient_id": patient_id,
+            "consent_type": consent.consent_type,
+            "consent_text_version": consent.consent_text_version,
+            "signature_method": consent.signature_method,
+            "expires_at": consent.expires_at,
+        }),
+    )
+    .await;
+
+    Ok((StatusCode::CREATED, Json(consent)))
+}
+
+/// Withdraw a patient consent
+///
+/// POST /api/v1/patients/:id/consents/:consent_id/withdraw
+///
+/// **RBAC**: Requires 'withdraw' permission on 'consents' resource
+/// **Roles**: ADMIN, DOCTOR, RECEPTIONIST
+pub async fn withdraw_patient_consent(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path((patient_id, consent_id)): Path<(Uuid, Uuid)>,
+    Json(req): Json<WithdrawConsentRequest>,
+) -> Result<Json<PatientConsentResponse>> {
+    check_permission(&state, &auth_user.role, "withdraw").await?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let consent = consent_service(&state)?
+        .withdraw_consent(
+            patient_id,
+            consent_id,
+            req.reason.as_deref(),
+            auth_user.user_id,
+        )
+        .await?;
+
+    audit_consent(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Update,
+        consent_id,
+        serde_json::json!({
+            "patient_id": patient_id,
+            "consent_type": consent.consent_type,
+            "status": consent.status,
+        }),
+    )
+    .await;
+
+    Ok(Json(consent))
+}
@@ -0,0 +1,203 @@
+/*!
+ * Access Delegation HTTP Handlers
+ *
+ * Lets administrators grant locum doctors time-limited access to a subset of
+ * another doctor's patients and appointments, and revoke it early.
+ * Doctors can see delegations they granted or received.
+ */
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::{
+    handlers::auth::AppState,
+    models::{
+        access_delegation::{CreateDelegationRequest, DelegationFilter, DelegationResponse},
+        AuditAction, AuditLog, CreateAuditLog, EntityType, RequestContext, UserRole,
+    },
+    services::DelegationService,
+    utils::{AppError, Result},
+};
+
+#[cfg(feature = "rbac")]
+use tracing::warn;
+
+/// Whether the role may perform `action` on delegations
+#[cfg(feature = "rbac")]
+async fn is_permitted(state: &AppState, user_role: &UserRole, action: &str) -> Result<bool> {
+    state
+        .enforcer
+        .enforce(user_role, "delegations", action)
+        .await
+        .map_err(|e| {
+            warn!("RBAC enforcement error: {}", e);
+            AppError::Internal("Failed to check permissions".to_string())
+        })
+}
+
+/// Fallback for non-RBAC builds - admins manage, doctors read their own
+#[cfg(not(feature = "rbac"))]
+async fn is_permitted(_state: &AppState, user_role: &UserRole, action: &str) -> Result<bool> {
+    Ok(match action {
+        "read_own" => true,
+        _ => *user_role == UserRole::Admin,
+    })
+}
+
+/// Check if user has permission to perform action on delegations
+async fn check_permission(state: &AppState, user_role: &UserRole, action: &str) -> Result<()> {
+    if !is_permitted(state, user_role, action).await? {
+        return Err(AppError::Forbidden(format!(
+            "User does not have permission to {} delegations",
+            action
+        )));
+    }
+
+    Ok(())
+}
+
+/// Grant a delegation
+///
+/// POST /api/v1/delegations
+pub async fn create_delegation(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(user_role): Extension<UserRole>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Json(req): Json<CreateDelegationRequest>,
+) -> Result<(StatusCode, Json<DelegationResponse>)> {
+    check_permission(&state, &user_role, "create").await?;
+
+    let now = Utc::now();
+    let delegation = DelegationService::new(state.pool.clone())
+        .create(&req, user_id, now)
+        .await?;
+
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(user_id),
+            action: AuditAction::Create,
+            entity_type: EntityType::AccessDelegation,
+            entity_id: Some(delegation.id.to_string()),
+            changes: Some(serde_json::json!({
+                "grantor_id": delegation.grantor_id,
+                "delegate_id": delegation.delegate_id,
+                "starts_at": delegation.starts_at,
+                "ends_at": delegation.ends_at,
+                "patient_count": delegation.patient_ids.len(),
+                "appointment_count": delegation.appointment_ids.len(),
+            })),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(DelegationResponse::from_delegation(delegation, now)),
+    ))
+}
+
+/// List delegations
+///
+/// GET /api/v1/delegations
+///
+/// Query parameters:
+/// - grantor_id, delegate_id: filter by user
+/// - include_inactive: include expired and revoked delegations (default false)
+///
+/// Non-admin users only see delegations they granted or received.
+pub async fn list_delegations(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(user_role): Extension<UserRole>,
+    Query(filter): Query<DelegationFilter>,
+) -> Result<Json<Vec<DelegationResponse>>> {
+    let involving = if is_permitted(&state, &user_role, "read").await? {
+        None
+    } else {
+        check_permission(&state, &user_role, "read_own").await?;
+        Some(user_id)
+    };
+
+    let now = Utc::now();
+    let delegations = DelegationService::new(state.pool.clone())
+        .list(&filter, involving)
+        .await?
+        .into_iter()
+        .map(|d| DelegationResponse::from_delegation(d, now))
+        .collect();
+
+    Ok(Json(delegations))
+}
+
+/// Get a delegation
+///
+/// GET /api/v1/delegations/{id}
+pub async fn get_delegation(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(user_role): Extension<UserRole>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<DelegationResponse>> {
+    let can_read_all = is_permitted(&state, &user_role, "read").await?;
+    if !can_read_all {
+        check_permission(&state, &user_role, "read_own").await?;
+    }
+
+    let delegation = DelegationService::new(state.pool.clone())
+        .get(id)
+        .await?
+        .filter(|d| can_read_all || d.involves(user_id))
+        .ok_or_else(|| AppError::NotFound("Delegation not found".to_string()))?;
+
+    Ok(Json(DelegationResponse::from_delegation(delegation, Utc::now())))
+}
+
+/// Revoke a delegation before its window ends
+///
+/// POST /api/v1/delegations/{id}/revoke
+pub async fn revoke_delegation(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(user_role): Extension<UserRole>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<DelegationResponse>> {
+    check_permission(&state, &user_role, "revoke").await?;
+
+    let now = Utc::now();
+    let delegation = DelegationService::new(state.pool.clone())
+        .revoke(id, user_id, now)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Delegation not found".to_string()))?;
+
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(user_id),
+            action: AuditAction::Update,
+            entity_type: EntityType::AccessDelegation,
+            entity_id: Some(id.to_string()),
+            changes: Some(serde_json::json!({
+                "action": "revoke",
+                "delegate_id": delegation.delegate_id,
+                "ends_at": delegation.ends_at,
+            })),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+
+    Ok(Json(DelegationResponse::from_delegation(delegation, now)))
+}
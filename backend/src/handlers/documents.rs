@@ -22,11 +22,16 @@ use crate::{
     handlers::auth::AppState,
     models::{
         AuditAction, AuditLog, AuthUser, CreateAuditLog, CreateDocumentTemplateRequest,
-        DeliverDocumentRequest, DocumentTemplateFilter, DocumentType, EntityType,
+        CreatePrintBundleRequest, DeliverDocumentRequest, DocumentTemplateFilter, DocumentType, EntityType,
         GenerateDocumentRequest, GeneratedDocumentFilter, RequestContext, TemplateLanguage,
         UpdateDocumentTemplateRequest, UserRole,
     },
-    services::{generate_document_email_body, DocumentService},
+    models::request_context::with_request_id_scope,
+    services::{
+        generate_document_email_body,
+        template_filters::{self, TemplateCatalogResponse},
+        BrandingService, DocumentRetry, DocumentService,
+    },
     utils::{AppError, Result},
 };
 
@@ -187,7 +192,8 @@ pub async fn create_document_template(
         std::env::var("DOCUMENT_STORAGE_PATH").unwrap_or_else(|_| "./documents".to_string()),
     );
 
-    let service = DocumentService::new(state.pool.clone(), encryption_key.clone(), storage_path);
+    let service = DocumentService::new(state.pool.clone(), encryption_key.clone(), storage_path)
+        .with_cache(state.reference_cache.clone());
     let template = service
         .create_template(req.clone(), auth_user.user_id)
         .await
@@ -238,7 +244,8 @@ pub async fn get_document_template(
         std::env::var("DOCUMENT_STORAGE_PATH").unwrap_or_else(|_| "./documents".to_string()),
     );
 
-    let service = DocumentService::new(state.pool.clone(), encryption_key.clone(), storage_path);
+    let service = DocumentService::new(state.pool.clone(), encryption_key.clone(), storage_path)
+        .with_cache(state.reference_cache.clone());
     let template = service
         .get_template(id)
         .await
@@ -282,7 +289,8 @@ pub async fn get_default_document_template(
         std::env::var("DOCUMENT_STORAGE_PATH").unwrap_or_else(|_| "./documents".to_string()),
     );
 
-    let service = DocumentService::new(state.pool.clone(), encryption_key.clone(), storage_path);
+    let service = DocumentService::new(state.pool.clone(), encryption_key.clone(), storage_path)
+        .with_cache(state.reference_cache.clone());
     let template = service
         .get_default_template(document_type, language)
         .await
@@ -301,6 +309,21 @@ pub async fn get_default_document_template(
     Ok(Json(template))
 }
 
+/// Template variable catalog
+///
+/// GET /api/v1/document-templates/variables
+///
+/// Lists the variables and the formatting filters/functions available to
+/// document templates, for use by the template editor.
+pub async fn get_template_variable_catalog(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<TemplateCatalogResponse>> {
+    check_template_permission(&state, &auth_user.role, "read").await?;
+
+    Ok(Json(template_filters::catalog()))
+}
+
 /// List document templates
 ///
 /// GET /api/v1/document-templates?document_type=...&language=...&is_active=true&limit=20&offset=0
@@ -328,7 +351,8 @@ pub async fn list_document_templates(
         std::env::var("DOCUMENT_STORAGE_PATH").unwrap_or_else(|_| "./documents".to_string()),
     );
 
-    let service = DocumentService::new(state.pool.clone(), encryption_key.clone(), storage_path);
+    let service = DocumentService::new(state.pool.clone(), encryption_key.clone(), storage_path)
+        .with_cache(state.reference_cache.clone());
     let result = service
         // Clamp page size to prevent uncontrolled allocation (CWE-770)
         .list_templates(filter, query.limit.unwrap_or(20).min(100), query.offset.unwrap_or(0))
@@ -365,7 +389,8 @@ pub async fn update_document_template(
         std::env::var("DOCUMENT_STORAGE_PATH").unwrap_or_else(|_| "./documents".to_string()),
     );
 
-    let service = DocumentService::new(state.pool.clone(), encryption_key.clone(), storage_path);
+    let service = DocumentService::new(state.pool.clone(), encryption_key.clone(), storage_path)
+        .with_cache(state.reference_cache.clone());
     let template = service
         .update_template(id, req.clone(), auth_user.user_id)
         .await
@@ -413,7 +438,8 @@ pub async fn delete_document_template(
         std::env::var("DOCUMENT_STORAGE_PATH").unwrap_or_else(|_| "./documents".to_string()),
     );
 
-    let service = DocumentService::new(state.pool.clone(), encryption_key.clone(), storage_path);
+    let service = DocumentService::new(state.pool.clone(), encryption_key.clone(), storage_path)
+        .with_cache(state.reference_cache.clone());
     service
         .delete_template(id, auth_user.user_id)
         .await
@@ -466,7 +492,8 @@ pub async fn generate_document(
         std::env::var("DOCUMENT_STORAGE_PATH").unwrap_or_else(|_| "./documents".to_string()),
     );
 
-    let service = DocumentService::new(state.pool.clone(), encryption_key.clone(), storage_path);
+    let service = DocumentService::new(state.pool.clone(), encryption_key.clone(), storage_path)
+        .with_cache(state.reference_cache.clone());
     let document = service
         .generate_document(req.clone(), auth_user.user_id)
         .await
@@ -501,6 +528,149 @@ pub async fn generate_document(
     Ok((StatusCode::CREATED, Json(document)))
 }
 
+/// Queue a patient chart print bundle
+///
+/// POST /api/v1/patients/:id/print-bundle
+///
+/// Returns 202 with the document in GENERATING state; the PDF is rendered in
+/// the background and clients poll GET /api/v1/documents/:id until it is
+/// GENERATED or FAILED.
+pub async fn create_print_bundle(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(patient_id): Path<Uuid>,
+    Json(req): Json<CreatePrintBundleRequest>,
+) -> Result<impl IntoResponse> {
+    check_document_permission(&state, &auth_user.role, "create").await?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let encryption_key = state
+        .encryption_key
+        .as_ref()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+
+    let storage_path = PathBuf::from(
+        std::env::var("DOCUMENT_STORAGE_PATH").unwrap_or_else(|_| "./documents".to_string()),
+    );
+
+    let service = DocumentService::new(state.pool.clone(), encryption_key.clone(), storage_path)
+        .with_cache(state.reference_cache.clone());
+    let document = service
+        .create_print_bundle(patient_id, &req, auth_user.user_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to queue print bundle for patient {}: {:?}", patient_id, e);
+            AppError::Internal(format!("Failed to queue print bundle: {:#}", e))
+        })?
+        .ok_or_else(|| AppError::NotFound(format!("Patient {} not found", patient_id)))?;
+
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(auth_user.user_id),
+            action: AuditAction::Create,
+            entity_type: EntityType::Document,
+            entity_id: Some(document.id.to_string()),
+            changes: Some(serde_json::json!({
+                "patient_id": patient_id,
+                "sections": req.resolved_sections(),
+                "type": "print_bundle",
+            })),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+
+    let document_id = document.id;
+    let provider_id = auth_user.user_id;
+    tokio::spawn(with_request_id_scope(request_ctx.request_id, async move {
+        if let Err(e) = service
+            .complete_print_bundle(document_id, patient_id, &req, provider_id)
+            .await
+        {
+            tracing::error!("Print bundle {} failed: {:#}", document_id, e);
+        }
+    }));
+
+    Ok((StatusCode::ACCEPTED, Json(document)))
+}
+
+/// Retry a failed document generation
+///
+/// POST /api/v1/documents/:id/retry
+///
+/// Re-runs generation from the stored original request after the underlying
+/// issue (missing font, broken template) has been fixed. The document keeps
+/// its id; the response is the document in GENERATED state, or FAILED with
+/// the new `generation_error` if the retry failed again.
+pub async fn retry_generated_document(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse> {
+    check_document_permission(&state, &auth_user.role, "create").await?;
+
+    let encryption_key = state
+        .encryption_key
+        .as_ref()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+
+    let storage_path = PathBuf::from(
+        std::env::var("DOCUMENT_STORAGE_PATH").unwrap_or_else(|_| "./documents".to_string()),
+    );
+
+    let service = DocumentService::new(state.pool.clone(), encryption_key.clone(), storage_path)
+        .with_cache(state.reference_cache.clone());
+    let retry = service
+        .retry_document(id, auth_user.user_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to retry document {}: {:?}", id, e);
+            AppError::Internal(format!("Failed to retry document: {:#}", e))
+        })?;
+
+    let (document, attempt, previous_error) = match retry {
+        DocumentRetry::NotFound => {
+            return Err(AppError::NotFound(format!("Document {} not found", id)));
+        }
+        DocumentRetry::NotRetryable(reason) => return Err(AppError::Conflict(reason)),
+        DocumentRetry::Retried {
+            document,
+            attempt,
+            previous_error,
+        } => (document, attempt, previous_error),
+    };
+
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(auth_user.user_id),
+            action: AuditAction::Update,
+            entity_type: EntityType::Document,
+            entity_id: Some(document.id.to_string()),
+            changes: Some(serde_json::json!({
+                "type": "retry",
+                "attempt": attempt,
+                "previous_error": previous_error,
+                "status": document.status,
+                "generation_error": document.generation_error,
+            })),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+
+    Ok(Json(document))
+}
+
 /// Get generated document by ID
 ///
 /// GET /api/v1/documents/:id
@@ -520,7 +690,8 @@ pub async fn get_generated_document(
         std::env::var("DOCUMENT_STORAGE_PATH").unwrap_or_else(|_| "./documents".to_string()),
     );
 
-    let service = DocumentService::new(state.pool.clone(), encryption_key.clone(), storage_path);
+    let service = DocumentService::new(state.pool.clone(), encryption_key.clone(), storage_path)
+        .with_cache(state.reference_cache.clone());
     let document = service
         .get_document(id, auth_user.user_id)
         .await
@@ -552,7 +723,8 @@ pub async fn download_document(
         std::env::var("DOCUMENT_STORAGE_PATH").unwrap_or_else(|_| "./documents".to_string()),
     );
 
-    let service = DocumentService::new(state.pool.clone(), encryption_key.clone(), storage_path);
+    let service = DocumentService::new(state.pool.clone(), encryption_key.clone(), storage_path)
+        .with_cache(state.reference_cache.clone());
 
     // Get document to verify it exists and get filename
     let document = service
@@ -564,9 +736,19 @@ pub async fn download_document(
         })?
         .ok_or_else(|| AppError::NotFound(format!("Document {} not found", id)))?;
 
+    stream_document_pdf(&service, id, &document.document_filename, auth_user.user_id).await
+}
+
+/// Stream a generated document's PDF as an attachment
+async fn stream_document_pdf(
+    service: &DocumentService,
+    id: Uuid,
+    filename: &str,
+    user_id: Uuid,
+) -> Result<([(header::HeaderName, String); 3], Body)> {
     // Get file path
     let file_path = service
-        .get_document_file_path(id, auth_user.user_id)
+        .get_document_file_path(id, user_id)
         .await
         .map_err(|e| {
             tracing::error!("Failed to get document file path {}: {}", id, e);
@@ -590,16 +772,146 @@ pub async fn download_document(
         (header::CONTENT_TYPE, "application/pdf".to_string()),
         (
             header::CONTENT_DISPOSITION,
-            format!("attachment; filename=\"{}\"", document.document_filename),
+            format!("attachment; filename=\"{}\"", filename),
+        ),
+        (
+            header::HeaderName::from_static("x-document-id"),
+            id.to_string(),
         ),
     ];
 
     Ok((headers, body))
 }
 
+// ==================== Clinical Summary Export ====================
+
+/// Export a visit's clinical summary as PDF
+///
+/// GET /api/v1/visits/:id/export/pdf
+///
+/// Renders demographics, vitals, SOAP notes, diagnoses and prescriptions of
+/// the visit on the built-in clinical summary template and returns the PDF.
+/// The document is also stored (VISIT_SUMMARY); its id is in the
+/// `X-Document-Id` header.
+pub async fn export_visit_summary_pdf(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(visit_id): Path<Uuid>,
+) -> Result<impl IntoResponse> {
+    check_document_permission(&state, &auth_user.role, "create").await?;
+
+    let service = summary_document_service(&state)?;
+    let document = service
+        .generate_visit_summary(visit_id, auth_user.user_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to export summary of visit {}: {:?}", visit_id, e);
+            AppError::Internal(format!("Failed to export visit summary: {:#}", e))
+        })?
+        .ok_or_else(|| AppError::NotFound(format!("Visit {} not found", visit_id)))?;
+
+    audit_summary_export(
+        &state,
+        &auth_user,
+        &request_ctx,
+        document.id,
+        serde_json::json!({
+            "patient_id": document.patient_id,
+            "visit_id": visit_id,
+            "type": "visit_summary",
+        }),
+    )
+    .await;
+
+    stream_document_pdf(&service, document.id, &document.document_filename, auth_user.user_id).await
+}
+
+/// Export a patient's full chart as PDF
+///
+/// GET /api/v1/patients/:id/export/pdf
+///
+/// Every visit with its vitals, SOAP notes, diagnoses and prescriptions,
+/// preceded by demographics, active problems and current medications. The
+/// document is also stored; its id is in the `X-Document-Id` header.
+pub async fn export_patient_chart_pdf(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(patient_id): Path<Uuid>,
+) -> Result<impl IntoResponse> {
+    check_document_permission(&state, &auth_user.role, "create").await?;
+
+    let service = summary_document_service(&state)?;
+    let document = service
+        .generate_chart_summary(patient_id, auth_user.user_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to export chart of patient {}: {:?}", patient_id, e);
+            AppError::Internal(format!("Failed to export patient chart: {:#}", e))
+        })?
+        .ok_or_else(|| AppError::NotFound(format!("Patient {} not found", patient_id)))?;
+
+    audit_summary_export(
+        &state,
+        &auth_user,
+        &request_ctx,
+        document.id,
+        serde_json::json!({
+            "patient_id": patient_id,
+            "type": "chart_summary",
+        }),
+    )
+    .await;
+
+    stream_document_pdf(&service, document.id, &document.document_filename, auth_user.user_id).await
+}
+
+fn summary_document_service(state: &AppState) -> Result<DocumentService> {
+    let encryption_key = state
+        .encryption_key
+        .as_ref()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+
+    let storage_path = PathBuf::from(
+        std::env::var("DOCUMENT_STORAGE_PATH").unwrap_or_else(|_| "./documents".to_string()),
+    );
+
+    Ok(
+        DocumentService::new(state.pool.clone(), encryption_key.clone(), storage_path)
+            .with_cache(state.reference_cache.clone()),
+    )
+}
+
+async fn audit_summary_export(
+    state: &AppState,
+    auth_user: &AuthUser,
+    request_ctx: &RequestContext,
+    document_id: Uuid,
+    changes: serde_json::Value,
+) {
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(auth_user.user_id),
+            action: AuditAction::Export,
+            entity_type: EntityType::Document,
+            entity_id: Some(document_id.to_string()),
+            changes: Some(changes),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+}
+
 /// List generated documents
 ///
 /// GET /api/v1/documents?patient_id=...&limit=20&offset=0
+///
+/// `status=FAILED` lists failed generations; each summary carries its
+/// `generation_error`.
 pub async fn list_generated_documents(
     State(state): State<AppState>,
     Extension(auth_user): Extension<AuthUser>,
@@ -617,8 +929,12 @@ pub async fn list_generated_documents(
             .and_then(|dt| DocumentType::from_str(dt)),
         status: query
             .status
-            .as_ref()
-            .and_then(|s| crate::models::DocumentStatus::from_str(s)),
+            .as_deref()
+            .map(|s| {
+                crate::models::DocumentStatus::from_str(&s.to_uppercase())
+                    .ok_or_else(|| AppError::BadRequest(format!("Invalid document status: {}", s)))
+            })
+            .transpose()?,
         is_signed: query.is_signed,
         from_date: query.from_date,
         to_date: query.to_date,
@@ -633,7 +949,8 @@ pub async fn list_generated_documents(
         std::env::var("DOCUMENT_STORAGE_PATH").unwrap_or_else(|_| "./documents".to_string()),
     );
 
-    let service = DocumentService::new(state.pool.clone(), encryption_key.clone(), storage_path);
+    let service = DocumentService::new(state.pool.clone(), encryption_key.clone(), storage_path)
+        .with_cache(state.reference_cache.clone());
     let result = service
         // Clamp page size to prevent uncontrolled allocation (CWE-770)
         .list_documents(filter, query.limit.unwrap_or(20).min(100), query.offset.unwrap_or(0), auth_user.user_id)
@@ -672,7 +989,8 @@ pub async fn deliver_document(
         std::env::var("DOCUMENT_STORAGE_PATH").unwrap_or_else(|_| "./documents".to_string()),
     );
 
-    let service = DocumentService::new(state.pool.clone(), encryption_key.clone(), storage_path.clone());
+    let service = DocumentService::new(state.pool.clone(), encryption_key.clone(), storage_path.clone())
+        .with_cache(state.reference_cache.clone());
 
     // Check if this is an email delivery
     let is_email_delivery = req.delivery_method.as_ref()
@@ -739,12 +1057,22 @@ pub async fn deliver_document(
         let practice_name = std::env::var("SMTP_FROM_NAME")
             .unwrap_or_else(|_| "DocPat Medical Practice".to_string());
 
+        // Practice branding (fall back to defaults rather than block delivery)
+        let branding = BrandingService::new(state.pool.clone())
+            .email_branding()
+            .await
+            .unwrap_or_else(|e| {
+                tracing::warn!("Failed to load email branding, using defaults: {}", e);
+                Default::default()
+            });
+
         // Generate email body
         let (plain_text, html) = generate_document_email_body(
             &patient_name,
             &document.document_title,
             &doctor_name,
             &practice_name,
+            &branding,
         );
 
         // Send the email
@@ -810,7 +1138,8 @@ pub async fn sign_document(
         std::env::var("DOCUMENT_STORAGE_PATH").unwrap_or_else(|_| "./documents".to_string()),
     );
 
-    let service = DocumentService::new(state.pool.clone(), encryption_key.clone(), storage_path);
+    let service = DocumentService::new(state.pool.clone(), encryption_key.clone(), storage_path)
+        .with_cache(state.reference_cache.clone());
     let document = service
         .sign_document(id, auth_user.user_id)
         .await
@@ -867,7 +1196,8 @@ pub async fn delete_generated_document(
         std::env::var("DOCUMENT_STORAGE_PATH").unwrap_or_else(|_| "./documents".to_string()),
     );
 
-    let service = DocumentService::new(state.pool.clone(), encryption_key.clone(), storage_path);
+    let service = DocumentService::new(state.pool.clone(), encryption_key.clone(), storage_path)
+        .with_cache(state.reference_cache.clone());
 
     // For non-admin users, check if document is signed before allowing deletion
     if !matches!(auth_user.role, UserRole::Admin) {
@@ -934,7 +1264,8 @@ pub async fn get_document_statistics(
         std::env::var("DOCUMENT_STORAGE_PATH").unwrap_or_else(|_| "./documents".to_string()),
     );
 
-    let service = DocumentService::new(state.pool.clone(), encryption_key.clone(), storage_path);
+    let service = DocumentService::new(state.pool.clone(), encryption_key.clone(), storage_path)
+        .with_cache(state.reference_cache.clone());
     let stats = service.get_statistics().await.map_err(|e| {
         tracing::error!("Failed to get document statistics: {}", e);
         AppError::Internal(format!("Failed to get statistics: {}", e))
@@ -56,7 +56,7 @@ async fn check_permission(
 ) -> Result<()> {
     // All authenticated users can read drug interactions
     // This is reference data needed for prescription safety
-    if !matches!(user_role, UserRole::Admin | UserRole::Doctor | UserRole::Nurse | UserRole::Staff) {
+    if !matches!(user_role, UserRole::Admin | UserRole::Doctor | UserRole::Nurse) {
         return Err(AppError::Forbidden(
             "Insufficient permissions".to_string(),
         ));
@@ -142,6 +142,8 @@ pub async fn check_new_medication_for_patient(
     let role_str = match auth_user.role {
         UserRole::Admin => "ADMIN",
         UserRole::Doctor => "DOCTOR",
+        UserRole::Nurse => "NURSE",
+        UserRole::Receptionist => "RECEPTIONIST",
     };
 
     let response = DrugInteractionService::check_new_medication_for_patient(
@@ -175,6 +177,8 @@ pub async fn check_patient_interactions(
     let role_str = match auth_user.role {
         UserRole::Admin => "ADMIN",
         UserRole::Doctor => "DOCTOR",
+        UserRole::Nurse => "NURSE",
+        UserRole::Receptionist => "RECEPTIONIST",
     };
 
     let response = DrugInteractionService::check_patient_interactions(
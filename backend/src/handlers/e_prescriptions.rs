@@ -0,0 +1,366 @@
+/*!
+ * E-Prescription Handlers
+ *
+ * Dematerialized prescriptions issued through Sistema TS.
+ *
+ * Endpoints:
+ * - POST /api/v1/prescriptions/:id/e-prescription - Submit the prescription and get its NRE
+ * - GET /api/v1/prescriptions/:id/e-prescription - NRE status and event history
+ * - POST /api/v1/prescriptions/:id/e-prescription/cancel - Cancel the NRE at Sistema TS
+ * - POST /api/v1/prescriptions/:id/e-prescription/promemoria - Generate the promemoria PDF
+ * - POST /api/v1/e-prescriptions/callback - Status callback (no JWT, shared secret)
+ */
+
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    Extension, Json,
+};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    handlers::auth::AppState,
+    models::{
+        AuditAction, AuditLog, AuthUser, CreateAuditLog, EPrescriptionCallback,
+        EPrescriptionResponse, EntityType, RequestContext, SubmitEPrescriptionRequest, UserRole,
+    },
+    services::{EPrescriptionService, SistemaTsClient},
+    utils::{AppError, EncryptionKey, Result},
+};
+
+#[cfg(feature = "pdf-export")]
+use crate::{
+    models::{GenerateNrePromemoriaRequest, NrePromemoriaResponse},
+    services::DocumentService,
+};
+#[cfg(feature = "pdf-export")]
+use axum::http::StatusCode;
+#[cfg(feature = "pdf-export")]
+use std::path::PathBuf;
+
+#[cfg(feature = "rbac")]
+use tracing::warn;
+
+/// Header carrying the shared secret of status callbacks
+const CALLBACK_SECRET_HEADER: &str = "x-callback-secret";
+
+/// Check if user has permission to perform action on prescriptions resource
+#[cfg(feature = "rbac")]
+async fn check_permission(state: &AppState, user_role: &UserRole, action: &str) -> Result<()> {
+    let has_permission = state
+        .enforcer
+        .enforce(user_role, "prescriptions", action)
+        .await
+        .map_err(|e| {
+            warn!("RBAC enforcement error: {}", e);
+            AppError::Internal("Failed to check permissions".to_string())
+        })?;
+
+    if !has_permission {
+        return Err(AppError::Forbidden(format!(
+            "User does not have permission to {} prescriptions",
+            action
+        )));
+    }
+
+    Ok(())
+}
+
+/// Fallback for non-RBAC builds - only checks role
+#[cfg(not(feature = "rbac"))]
+async fn check_permission(_state: &AppState, user_role: &UserRole, action: &str) -> Result<()> {
+    let allowed = match action {
+        "read" | "promemoria" => matches!(
+            user_role,
+            UserRole::Admin | UserRole::Doctor | UserRole::Nurse
+        ),
+        _ => matches!(user_role, UserRole::Admin | UserRole::Doctor),
+    };
+
+    if !allowed {
+        return Err(AppError::Forbidden("Insufficient permissions".to_string()));
+    }
+    Ok(())
+}
+
+fn service_encryption_key(state: &AppState) -> Result<EncryptionKey> {
+    state
+        .encryption_key
+        .clone()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))
+}
+
+fn e_prescription_service(state: &AppState) -> Result<EPrescriptionService> {
+    Ok(EPrescriptionService::new(
+        state.pool.clone(),
+        service_encryption_key(state)?,
+    ))
+}
+
+fn sistema_ts_client(state: &AppState) -> Result<&SistemaTsClient> {
+    state.sistema_ts.as_ref().ok_or_else(|| {
+        AppError::BadRequest("Sistema TS e-prescriptions are not configured".to_string())
+    })
+}
+
+fn role_name(auth_user: &AuthUser) -> String {
+    format!("{:?}", auth_user.role).to_uppercase()
+}
+
+/// Audit an e-prescription change on the prescription
+async fn audit_e_prescription(
+    state: &AppState,
+    user_id: Option<Uuid>,
+    request_ctx: &RequestContext,
+    prescription_id: Uuid,
+    changes: serde_json::Value,
+) {
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id,
+            action: AuditAction::Update,
+            entity_type: EntityType::Prescription,
+            entity_id: Some(prescription_id.to_string()),
+            changes: Some(changes),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+}
+
+/// Submit a prescription to Sistema TS
+///
+/// POST /api/v1/prescriptions/:id/e-prescription
+///
+/// Returns the NRE with status ISSUED, or status REJECTED with the Sistema
+/// TS errors on the latest event.
+///
+/// **RBAC**: Requires 'e_prescribe' permission on 'prescriptions' resource
+pub async fn submit_e_prescription(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(prescription_id): Path<Uuid>,
+    Json(req): Json<SubmitEPrescriptionRequest>,
+) -> Result<Json<EPrescriptionResponse>> {
+    check_permission(&state, &auth_user.role, "e_prescribe").await?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let response = e_prescription_service(&state)?
+        .submit(
+            sistema_ts_client(&state)?,
+            prescription_id,
+            &req,
+            auth_user.user_id,
+            &role_name(&auth_user),
+        )
+        .await?;
+
+    audit_e_prescription(
+        &state,
+        Some(auth_user.user_id),
+        &request_ctx,
+        prescription_id,
+        serde_json::json!({
+            "action": "e_prescription_submitted",
+            "nre": response.nre,
+            "status": response.status,
+        }),
+    )
+    .await;
+
+    Ok(Json(response))
+}
+
+/// Get the NRE status of a prescription with its event history
+///
+/// GET /api/v1/prescriptions/:id/e-prescription
+///
+/// **RBAC**: Requires 'read' permission on 'prescriptions' resource
+pub async fn get_e_prescription(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(prescription_id): Path<Uuid>,
+) -> Result<Json<EPrescriptionResponse>> {
+    check_permission(&state, &auth_user.role, "read").await?;
+
+    let response = e_prescription_service(&state)?
+        .get_status(prescription_id, auth_user.user_id, &role_name(&auth_user))
+        .await?;
+
+    Ok(Json(response))
+}
+
+/// Cancel the NRE of a prescription at Sistema TS
+///
+/// POST /api/v1/prescriptions/:id/e-prescription/cancel
+///
+/// Only an NRE that has not been dispensed can be cancelled. The
+/// prescription itself keeps its status.
+///
+/// **RBAC**: Requires 'e_prescribe' permission on 'prescriptions' resource
+pub async fn cancel_e_prescription(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(prescription_id): Path<Uuid>,
+) -> Result<Json<EPrescriptionResponse>> {
+    check_permission(&state, &auth_user.role, "e_prescribe").await?;
+
+    let response = e_prescription_service(&state)?
+        .cancel(
+            sistema_ts_client(&state)?,
+            prescription_id,
+            auth_user.user_id,
+            &role_name(&auth_user),
+        )
+        .await?;
+
+    audit_e_prescription(
+        &state,
+        Some(auth_user.user_id),
+        &request_ctx,
+        prescription_id,
+        serde_json::json!({
+            "action": "e_prescription_cancelled",
+            "nre": response.nre,
+        }),
+    )
+    .await;
+
+    Ok(Json(response))
+}
+
+/// Generate the promemoria PDF of a prescription's NRE
+///
+/// POST /api/v1/prescriptions/:id/e-prescription/promemoria
+///
+/// The promemoria is what the patient takes to the pharmacy: NRE and fiscal
+/// code with their barcodes, and the prescribed medication.
+///
+/// **RBAC**: Requires 'promemoria' permission on 'prescriptions' resource
+#[cfg(feature = "pdf-export")]
+pub async fn generate_nre_promemoria(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(prescription_id): Path<Uuid>,
+    Json(req): Json<GenerateNrePromemoriaRequest>,
+) -> Result<(StatusCode, Json<NrePromemoriaResponse>)> {
+    check_permission(&state, &auth_user.role, "promemoria").await?;
+
+    let service = e_prescription_service(&state)?;
+    let role = role_name(&auth_user);
+    let mut promemoria = service
+        .prepare_promemoria(prescription_id, auth_user.user_id, &role)
+        .await?;
+    promemoria.prescriber_fiscal_code = state
+        .sistema_ts
+        .as_ref()
+        .map(|client| client.prescriber_fiscal_code().to_string());
+
+    let storage_path = PathBuf::from(
+        std::env::var("DOCUMENT_STORAGE_PATH").unwrap_or_else(|_| "./documents".to_string()),
+    );
+    let document = DocumentService::new(
+        state.pool.clone(),
+        service_encryption_key(&state)?,
+        storage_path,
+    )
+    .with_cache(state.reference_cache.clone())
+    .generate_nre_promemoria(&promemoria, &req, auth_user.user_id)
+    .await
+    .map_err(|e| {
+        tracing::error!(
+            "Failed to generate promemoria for prescription {}: {:?}",
+            prescription_id,
+            e
+        );
+        AppError::Internal(format!("Failed to generate promemoria: {:#}", e))
+    })?;
+
+    let e_prescription = service
+        .get_status(prescription_id, auth_user.user_id, &role)
+        .await?;
+
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(auth_user.user_id),
+            action: AuditAction::Create,
+            entity_type: EntityType::Prescription,
+            entity_id: Some(prescription_id.to_string()),
+            changes: Some(serde_json::json!({
+                "document_id": document.id,
+                "type": "nre_promemoria",
+                "nre": promemoria.nre,
+            })),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(NrePromemoriaResponse {
+            e_prescription,
+            document,
+        }),
+    ))
+}
+
+/// Apply a Sistema TS status callback
+///
+/// POST /api/v1/e-prescriptions/callback
+///
+/// Not JWT-authenticated: the caller must send the configured callback
+/// secret in the `X-Callback-Secret` header. Redelivering a status already
+/// applied is accepted.
+pub async fn e_prescription_callback(
+    State(state): State<AppState>,
+    Extension(request_ctx): Extension<RequestContext>,
+    headers: HeaderMap,
+    Json(callback): Json<EPrescriptionCallback>,
+) -> Result<Json<EPrescriptionResponse>> {
+    let client = sistema_ts_client(&state)?;
+    let secret = headers
+        .get(CALLBACK_SECRET_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    if !client.verify_callback_secret(secret) {
+        return Err(AppError::Unauthorized(
+            "Invalid e-prescription callback secret".to_string(),
+        ));
+    }
+
+    callback
+        .validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let response = e_prescription_service(&state)?
+        .apply_callback(&callback)
+        .await?;
+
+    audit_e_prescription(
+        &state,
+        None,
+        &request_ctx,
+        response.prescription_id,
+        serde_json::json!({
+            "action": "e_prescription_status_callback",
+            "nre": response.nre,
+            "status": callback.status,
+        }),
+    )
+    .await;
+
+    Ok(Json(response))
+}
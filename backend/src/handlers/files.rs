@@ -126,6 +126,15 @@ pub async fn upload_file(
                     )
                 })?;
                 purpose = FilePurpose::from_str(&value).unwrap_or(FilePurpose::Attachment);
+                if purpose.is_patient_data() {
+                    return Err((
+                        StatusCode::BAD_REQUEST,
+                        error_response(
+                            "INVALID_PURPOSE",
+                            "Patient files must be uploaded through the patient endpoints",
+                        ),
+                    ));
+                }
             }
             "alt_text" => {
                 alt_text = Some(field.text().await.map_err(|_| {
@@ -230,6 +239,7 @@ pub async fn get_file_metadata(
     #[cfg(feature = "rbac")]
     check_permission(&state.enforcer, &_user_role, "files", "read").await?;
 
+    // Encrypted patient files are only served through the patient endpoints
     let file = FileUploadService::get_file(&state.pool, file_id)
         .await
         .map_err(|e| {
@@ -239,6 +249,7 @@ pub async fn get_file_metadata(
                 error_response("INTERNAL_ERROR", "Failed to retrieve file"),
             )
         })?
+        .filter(|file| !file.is_encrypted)
         .ok_or_else(|| {
             (
                 StatusCode::NOT_FOUND,
@@ -272,6 +283,7 @@ pub async fn download_file(
                 error_response("INTERNAL_ERROR", "Failed to retrieve file"),
             )
         })?
+        .filter(|(file, _)| !file.is_encrypted)
         .ok_or_else(|| {
             (
                 StatusCode::NOT_FOUND,
@@ -342,6 +354,7 @@ pub async fn serve_file(
                 error_response("INTERNAL_ERROR", "Failed to retrieve file"),
             )
         })?
+        .filter(|(file, _)| !file.is_encrypted)
         .ok_or_else(|| {
             (
                 StatusCode::NOT_FOUND,
@@ -0,0 +1,344 @@
+/*!
+ * Imaging Handlers
+ *
+ * Imaging orders on the patient record.
+ *
+ * Endpoints:
+ * - GET /api/v1/patients/:id/imaging-orders - List a patient's imaging orders
+ * - POST /api/v1/patients/:id/imaging-orders - Place an imaging order
+ * - GET /api/v1/patients/:id/imaging-orders/:order_id - Get an imaging order
+ * - PUT /api/v1/patients/:id/imaging-orders/:order_id - Update an imaging order
+ * - DELETE /api/v1/patients/:id/imaging-orders/:order_id - Delete an imaging order
+ * - POST /api/v1/patients/:id/imaging-orders/:order_id/referral-letter - Generate the referral letter PDF
+ */
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    handlers::auth::AppState,
+    models::{
+        AuditAction, AuditLog, AuthUser, CreateAuditLog, CreateImagingOrderRequest, EntityType,
+        ImagingOrderResponse, ListImagingOrdersQuery, RequestContext, UpdateImagingOrderRequest,
+        UserRole,
+    },
+    services::ImagingService,
+    utils::{AppError, EncryptionKey, Result},
+};
+
+#[cfg(feature = "pdf-export")]
+use crate::{
+    models::{GenerateImagingReferralRequest, ImagingReferralResponse},
+    services::DocumentService,
+};
+#[cfg(feature = "pdf-export")]
+use std::path::PathBuf;
+
+#[cfg(feature = "rbac")]
+use tracing::warn;
+
+/// Check if user has permission to perform action on imaging resource
+#[cfg(feature = "rbac")]
+async fn check_permission(state: &AppState, user_role: &UserRole, action: &str) -> Result<()> {
+    let has_permission = state
+        .enforcer
+        .enforce(user_role, "imaging", action)
+        .await
+        .map_err(|e| {
+            warn!("RBAC enforcement error: {}", e);
+            AppError::Internal("Failed to check permissions".to_string())
+        })?;
+
+    if !has_permission {
+        return Err(AppError::Forbidden(format!(
+            "User does not have permission to {} imaging",
+            action
+        )));
+    }
+
+    Ok(())
+}
+
+/// Fallback for non-RBAC builds - only checks role
+#[cfg(not(feature = "rbac"))]
+async fn check_permission(_state: &AppState, user_role: &UserRole, action: &str) -> Result<()> {
+    let allowed = match action {
+        "read" => matches!(
+            user_role,
+            UserRole::Admin | UserRole::Doctor | UserRole::Nurse
+        ),
+        _ => matches!(user_role, UserRole::Admin | UserRole::Doctor),
+    };
+
+    if !allowed {
+        return Err(AppError::Forbidden("Insufficient permissions".to_string()));
+    }
+    Ok(())
+}
+
+fn service_encryption_key(state: &AppState) -> Result<EncryptionKey> {
+    state
+        .encryption_key
+        .clone()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))
+}
+
+fn imaging_service(state: &AppState) -> Result<ImagingService> {
+    Ok(ImagingService::new(
+        state.pool.clone(),
+        service_encryption_key(state)?,
+    ))
+}
+
+/// Audit an imaging order change; the payload never contains the clinical
+/// question or the report
+async fn audit_imaging(
+    state: &AppState,
+    auth_user: &AuthUser,
+    request_ctx: &RequestContext,
+    action: AuditAction,
+    order_id: Uuid,
+    changes: serde_json::Value,
+) {
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(auth_user.user_id),
+            action,
+            entity_type: EntityType::ImagingOrder,
+            entity_id: Some(order_id.to_string()),
+            changes: Some(changes),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+}
+
+/// List a patient's imaging orders
+///
+/// GET /api/v1/patients/:id/imaging-orders
+///
+/// **RBAC**: Requires 'read' permission on 'imaging' resource
+pub async fn list_imaging_orders(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(patient_id): Path<Uuid>,
+    Query(query): Query<ListImagingOrdersQuery>,
+) -> Result<Json<Vec<ImagingOrderResponse>>> {
+    check_permission(&state, &auth_user.role, "read").await?;
+
+    let orders = imaging_service(&state)?
+        .list_orders(patient_id, &query, auth_user.user_id)
+        .await?;
+
+    Ok(Json(orders))
+}
+
+/// Place an imaging order
+///
+/// POST /api/v1/patients/:id/imaging-orders
+///
+/// **RBAC**: Requires 'create' permission on 'imaging' resource
+pub async fn create_imaging_order(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(patient_id): Path<Uuid>,
+    Json(req): Json<CreateImagingOrderRequest>,
+) -> Result<(StatusCode, Json<ImagingOrderResponse>)> {
+    check_permission(&state, &auth_user.role, "create").await?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let order = imaging_service(&state)?
+        .create_order(patient_id, &req, auth_user.user_id)
+        .await?;
+
+    audit_imaging(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Create,
+        order.id,
+        serde_json::json!({
+            "patient_id": patient_id,
+            "visit_id": order.visit_id,
+            "modality": order.modality,
+            "body_part": order.body_part,
+            "urgency": order.urgency,
+        }),
+    )
+    .await;
+
+    Ok((StatusCode::CREATED, Json(order)))
+}
+
+/// Get an imaging order
+///
+/// GET /api/v1/patients/:id/imaging-orders/:order_id
+///
+/// **RBAC**: Requires 'read' permission on 'imaging' resource
+pub async fn get_imaging_order(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path((patient_id, order_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<ImagingOrderResponse>> {
+    check_permission(&state, &auth_user.role, "read").await?;
+
+    let order = imaging_service(&state)?
+        .get_order(patient_id, order_id, auth_user.user_id)
+        .await?;
+
+    Ok(Json(order))
+}
+
+/// Update an imaging order
+///
+/// PUT /api/v1/patients/:id/imaging-orders/:order_id
+///
+/// Also records the exam as performed or reported through `status`.
+///
+/// **RBAC**: Requires 'update' permission on 'imaging' resource
+pub async fn update_imaging_order(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path((patient_id, order_id)): Path<(Uuid, Uuid)>,
+    Json(req): Json<UpdateImagingOrderRequest>,
+) -> Result<Json<ImagingOrderResponse>> {
+    check_permission(&state, &auth_user.role, "update").await?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let order = imaging_service(&state)?
+        .update_order(patient_id, order_id, &req, auth_user.user_id)
+        .await?;
+
+    audit_imaging(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Update,
+        order_id,
+        serde_json::json!({
+            "patient_id": patient_id,
+            "status": req.status,
+            "urgency": req.urgency,
+            "report_changed": req.report.is_some(),
+        }),
+    )
+    .await;
+
+    Ok(Json(order))
+}
+
+/// Delete an imaging order
+///
+/// DELETE /api/v1/patients/:id/imaging-orders/:order_id
+///
+/// The order is marked deleted and kept with the medical record.
+///
+/// **RBAC**: Requires 'delete' permission on 'imaging' resource
+pub async fn delete_imaging_order(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path((patient_id, order_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode> {
+    check_permission(&state, &auth_user.role, "delete").await?;
+
+    imaging_service(&state)?
+        .delete_order(patient_id, order_id, auth_user.user_id)
+        .await?;
+
+    audit_imaging(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Delete,
+        order_id,
+        serde_json::json!({ "patient_id": patient_id }),
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Generate the referral letter PDF for an imaging order
+///
+/// POST /api/v1/patients/:id/imaging-orders/:order_id/referral-letter
+///
+/// Renders a REFERRAL_LETTER template with the order's exam and clinical
+/// question and links the document to the order.
+///
+/// **RBAC**: Requires 'referral' permission on 'imaging' resource
+#[cfg(feature = "pdf-export")]
+pub async fn generate_imaging_referral(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path((patient_id, order_id)): Path<(Uuid, Uuid)>,
+    Json(req): Json<GenerateImagingReferralRequest>,
+) -> Result<(StatusCode, Json<ImagingReferralResponse>)> {
+    check_permission(&state, &auth_user.role, "referral").await?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let service = imaging_service(&state)?;
+    let order = service
+        .prepare_referral(patient_id, order_id, auth_user.user_id)
+        .await?;
+
+    let storage_path = PathBuf::from(
+        std::env::var("DOCUMENT_STORAGE_PATH").unwrap_or_else(|_| "./documents".to_string()),
+    );
+    let document = DocumentService::new(
+        state.pool.clone(),
+        service_encryption_key(&state)?,
+        storage_path,
+    )
+    .with_cache(state.reference_cache.clone())
+    .generate_imaging_referral(&order, &req, auth_user.user_id)
+    .await
+    .map_err(|e| {
+        tracing::error!(
+            "Failed to generate referral letter for imaging order {}: {:?}",
+            order_id,
+            e
+        );
+        AppError::Internal(format!("Failed to generate referral letter: {:#}", e))
+    })?;
+
+    let order = service
+        .link_referral(patient_id, order_id, document.id, auth_user.user_id)
+        .await?;
+
+    audit_imaging(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Create,
+        order_id,
+        serde_json::json!({
+            "patient_id": patient_id,
+            "document_id": document.id,
+            "type": "referral_letter",
+        }),
+    )
+    .await;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(ImagingReferralResponse { order, document }),
+    ))
+}
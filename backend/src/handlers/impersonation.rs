@@ -0,0 +1,257 @@
+//! Impersonation Handlers
+//!
+//! Lets an ADMIN act as another user for support and troubleshooting.
+//! Sessions are short-lived, recorded in `impersonation_sessions`, and every
+//! audit row written during them names both the user and the admin.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::handlers::auth::AppState;
+use crate::models::impersonation::{
+    Impersonation, ImpersonationSession, StartImpersonationRequest, StartImpersonationResponse,
+};
+use crate::models::user::{User, UserRole};
+use crate::models::{AuditAction, AuditLog, CreateAuditLog, EntityType, RequestContext};
+use crate::services::ImpersonationService;
+
+#[cfg(feature = "rbac")]
+use crate::utils::permissions::require_admin;
+
+/// Error response body
+fn error_response(error: &str, message: &str) -> Json<serde_json::Value> {
+    Json(serde_json::json!({
+        "error": error,
+        "message": message
+    }))
+}
+
+/// Query parameters for listing impersonation sessions
+#[derive(Debug, Deserialize)]
+pub struct ListImpersonationSessionsQuery {
+    pub impersonator_id: Option<Uuid>,
+    pub target_user_id: Option<Uuid>,
+    #[serde(default = "default_limit")]
+    pub limit: i64,
+}
+
+fn default_limit() -> i64 {
+    50
+}
+
+/// Start impersonating a user (ADMIN only)
+///
+/// POST /api/v1/users/{id}/impersonate
+///
+/// Returns an access token acting as the user. Admins cannot be impersonated.
+pub async fn start_impersonation(
+    State(state): State<AppState>,
+    Path(target_user_id): Path<Uuid>,
+    Extension(current_user_id): Extension<Uuid>,
+    Extension(user_role): Extension<UserRole>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Json(req): Json<StartImpersonationRequest>,
+) -> Result<(StatusCode, Json<StartImpersonationResponse>), (StatusCode, Json<serde_json::Value>)> {
+    let pool = &state.pool;
+    #[cfg(feature = "rbac")]
+    require_admin(&user_role)?;
+
+    let reason = req
+        .validated_reason()
+        .map_err(|message| (StatusCode::BAD_REQUEST, error_response("VALIDATION_ERROR", &message)))?;
+
+    if target_user_id == current_user_id {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            error_response("INVALID_TARGET", "You cannot impersonate yourself"),
+        ));
+    }
+
+    let target = User::find_by_id(pool, &target_user_id).await.map_err(|e| {
+        tracing::error!("Database error fetching user for impersonation: {}", e);
+        (
+            StatusCode::NOT_FOUND,
+            error_response("USER_NOT_FOUND", "User not found"),
+        )
+    })?;
+
+    if target.role == UserRole::Admin {
+        return Err((
+            StatusCode::FORBIDDEN,
+            error_response("INVALID_TARGET", "Administrators cannot be impersonated"),
+        ));
+    }
+    if !target.is_active {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            error_response("INVALID_TARGET", "Inactive users cannot be impersonated"),
+        ));
+    }
+
+    let expires_at = ImpersonationSession::expiry_from(Utc::now());
+    let (access_token, claims) = state
+        .auth_service
+        .generate_impersonation_token(&target.id, &target.role, &current_user_id, expires_at)
+        .map_err(|e| {
+            tracing::error!("Failed to issue impersonation token: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                error_response("INTERNAL_ERROR", "Failed to start impersonation"),
+            )
+        })?;
+
+    let session = ImpersonationService::new(pool.clone())
+        .create(
+            current_user_id,
+            target.id,
+            reason,
+            &claims.jti,
+            expires_at,
+            request_ctx.ip_address.as_deref(),
+        )
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to record impersonation session: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                error_response("INTERNAL_ERROR", "Failed to start impersonation"),
+            )
+        })?;
+
+    let _ = AuditLog::create(
+        pool,
+        CreateAuditLog {
+            user_id: Some(current_user_id),
+            action: AuditAction::Create,
+            entity_type: EntityType::User,
+            entity_id: Some(target.id.to_string()),
+            changes: Some(serde_json::json!({
+                "action": "impersonation_started",
+                "session_id": session.id,
+                "reason": reason,
+                "expires_at": expires_at,
+            })),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+
+    tracing::warn!(
+        "Admin {} started impersonating user {} (session {})",
+        current_user_id,
+        target.id,
+        session.id
+    );
+
+    Ok((
+        StatusCode::CREATED,
+        Json(StartImpersonationResponse {
+            session_id: session.id,
+            access_token,
+            expires_in: (expires_at - Utc::now()).num_seconds().max(0),
+            expires_at,
+            user_id: target.id,
+            username: target.username,
+            role: target.role.to_string(),
+        }),
+    ))
+}
+
+/// End the current impersonation session
+///
+/// POST /api/v1/auth/impersonation/end
+///
+/// Must be called with the impersonation token, which is revoked.
+pub async fn end_impersonation(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(request_ctx): Extension<RequestContext>,
+    impersonation: Option<Extension<Impersonation>>,
+) -> Result<StatusCode, (StatusCode, Json<serde_json::Value>)> {
+    let Some(Extension(impersonation)) = impersonation else {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            error_response("NOT_IMPERSONATING", "This token is not an impersonation token"),
+        ));
+    };
+
+    let session = end_session(&state, &impersonation).await;
+
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(user_id),
+            action: AuditAction::Update,
+            entity_type: EntityType::User,
+            entity_id: Some(user_id.to_string()),
+            changes: Some(serde_json::json!({
+                "action": "impersonation_ended",
+                "session_id": session.map(|s| s.id),
+            })),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Revoke an impersonation token and close its session
+///
+/// Also used by logout, so logging out of an impersonation session does not
+/// end the impersonated user's own session.
+pub(crate) async fn end_session(
+    state: &AppState,
+    impersonation: &Impersonation,
+) -> Option<ImpersonationSession> {
+    state
+        .token_denylist
+        .insert(&impersonation.jti, impersonation.expires_at);
+
+    ImpersonationService::new(state.pool.clone())
+        .end(&impersonation.jti)
+        .await
+        .unwrap_or_else(|e| {
+            tracing::error!("Failed to close impersonation session: {}", e);
+            None
+        })
+}
+
+/// List impersonation sessions (ADMIN only)
+///
+/// GET /api/v1/users/impersonations
+pub async fn list_impersonation_sessions(
+    State(state): State<AppState>,
+    Extension(user_role): Extension<UserRole>,
+    Query(query): Query<ListImpersonationSessionsQuery>,
+) -> Result<Json<Vec<ImpersonationSession>>, (StatusCode, Json<serde_json::Value>)> {
+    #[cfg(feature = "rbac")]
+    require_admin(&user_role)?;
+
+    let sessions = ImpersonationService::new(state.pool.clone())
+        .list(
+            query.impersonator_id,
+            query.target_user_id,
+            query.limit.clamp(1, 500),
+        )
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to list impersonation sessions: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                error_response("INTERNAL_ERROR", "Failed to list impersonation sessions"),
+            )
+        })?;
+
+    Ok(Json(sessions))
+}
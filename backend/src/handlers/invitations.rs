@@ -0,0 +1,359 @@
+/*!
+ * User Invitation HTTP Handlers
+ *
+ * Admin endpoints to invite staff by email with a pre-assigned role, and the
+ * public endpoints the invitee uses to accept: look up the invitation, get an
+ * MFA enrollment secret, then choose credentials and confirm MFA in one step.
+ *
+ * Invitation tokens are always sent in the request body (never the URL) so
+ * they do not end up in audit or proxy logs.
+ */
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::handlers::auth::AppState;
+use crate::handlers::mfa::{generate_mfa_setup, verify_totp_code, MfaSetupResponse};
+use crate::models::user_invitation::{
+    AcceptInvitationRequest, CreateInvitationRequest, InvitationResponse, InvitationStatus,
+    InvitationTokenRequest, ListInvitationsQuery, PublicInvitationResponse,
+    SendInvitationResponse,
+};
+use crate::models::{AuditAction, AuditLog, CreateAuditLog, EntityType, RequestContext, UserRole};
+use crate::services::invitation_service::{InvitationCredentials, InvitationService};
+use crate::utils::password::validate_password;
+use crate::utils::{AppError, PasswordHasherUtil, Result};
+
+/// Only administrators can manage invitations
+fn ensure_admin(user_role: &UserRole) -> Result<()> {
+    if *user_role != UserRole::Admin {
+        return Err(AppError::Forbidden(
+            "Only administrators can manage invitations".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Write an audit entry for an invitation event
+async fn audit_invitation(
+    state: &AppState,
+    user_id: Option<Uuid>,
+    action: AuditAction,
+    invitation_id: Uuid,
+    changes: serde_json::Value,
+    request_ctx: &RequestContext,
+) {
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id,
+            action,
+            entity_type: EntityType::UserInvitation,
+            entity_id: Some(invitation_id.to_string()),
+            changes: Some(changes),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+}
+
+// ============================================================================
+// Admin Endpoints
+// ============================================================================
+
+/// Invite a new user by email
+///
+/// POST /api/v1/users/invitations
+///
+/// The invitee receives an email with a single-use link. If email delivery is
+/// unavailable, the link is returned in the response instead.
+pub async fn create_invitation(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(user_role): Extension<UserRole>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Json(request): Json<CreateInvitationRequest>,
+) -> Result<(StatusCode, Json<SendInvitationResponse>)> {
+    ensure_admin(&user_role)?;
+
+    request
+        .validate()
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+
+    let service = InvitationService::new(state.pool.clone());
+    let (invitation, token) = service
+        .create_invitation(&state.settings_service, &request, user_id)
+        .await?;
+
+    let (url, email_sent) = service
+        .send_invitation_email(state.email_service.as_ref(), &invitation, &token)
+        .await;
+
+    audit_invitation(
+        &state,
+        Some(user_id),
+        AuditAction::Create,
+        invitation.id,
+        serde_json::json!({
+            "email": invitation.email,
+            "role": invitation.role.to_string(),
+            "expires_at": invitation.expires_at,
+            "email_sent": email_sent,
+        }),
+        &request_ctx,
+    )
+    .await;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(SendInvitationResponse {
+            invitation: InvitationResponse::from(invitation),
+            email_sent,
+            invitation_url: (!email_sent).then_some(url),
+        }),
+    ))
+}
+
+/// List invitations
+///
+/// GET /api/v1/users/invitations?status=PENDING
+pub async fn list_invitations(
+    State(state): State<AppState>,
+    Extension(user_role): Extension<UserRole>,
+    Query(query): Query<ListInvitationsQuery>,
+) -> Result<Json<Vec<InvitationResponse>>> {
+    ensure_admin(&user_role)?;
+
+    let status = match query.status.as_deref() {
+        Some(s) => Some(InvitationStatus::from_str(s).ok_or_else(|| {
+            AppError::Validation(format!("Invalid invitation status: {}", s))
+        })?),
+        None => None,
+    };
+
+    let invitations = InvitationService::new(state.pool.clone())
+        .list_invitations(status)
+        .await?;
+
+    Ok(Json(
+        invitations
+            .into_iter()
+            .map(InvitationResponse::from)
+            .collect(),
+    ))
+}
+
+/// Re-send an invitation with a fresh link and validity window
+///
+/// POST /api/v1/users/invitations/:id/resend
+///
+/// The previous link stops working.
+pub async fn resend_invitation(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(user_role): Extension<UserRole>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<SendInvitationResponse>> {
+    ensure_admin(&user_role)?;
+
+    let service = InvitationService::new(state.pool.clone());
+    let (invitation, token) = service
+        .renew_invitation(&state.settings_service, id)
+        .await?;
+
+    let (url, email_sent) = service
+        .send_invitation_email(state.email_service.as_ref(), &invitation, &token)
+        .await;
+
+    audit_invitation(
+        &state,
+        Some(user_id),
+        AuditAction::Update,
+        invitation.id,
+        serde_json::json!({
+            "action": "resend",
+            "expires_at": invitation.expires_at,
+            "email_sent": email_sent,
+        }),
+        &request_ctx,
+    )
+    .await;
+
+    Ok(Json(SendInvitationResponse {
+        invitation: InvitationResponse::from(invitation),
+        email_sent,
+        invitation_url: (!email_sent).then_some(url),
+    }))
+}
+
+/// Revoke an open invitation
+///
+/// DELETE /api/v1/users/invitations/:id
+pub async fn revoke_invitation(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(user_role): Extension<UserRole>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<InvitationResponse>> {
+    ensure_admin(&user_role)?;
+
+    let invitation = InvitationService::new(state.pool.clone())
+        .revoke_invitation(id)
+        .await?;
+
+    audit_invitation(
+        &state,
+        Some(user_id),
+        AuditAction::Delete,
+        invitation.id,
+        serde_json::json!({ "action": "revoke", "email": invitation.email }),
+        &request_ctx,
+    )
+    .await;
+
+    Ok(Json(InvitationResponse::from(invitation)))
+}
+
+// ============================================================================
+// Invitee Endpoints (no authentication)
+// ============================================================================
+
+/// Look up an invitation from its token
+///
+/// POST /api/v1/auth/invitations/lookup
+pub async fn lookup_invitation(
+    State(state): State<AppState>,
+    Json(request): Json<InvitationTokenRequest>,
+) -> Result<Json<PublicInvitationResponse>> {
+    let invitation = InvitationService::new(state.pool.clone())
+        .find_open_by_token(&request.token)
+        .await?;
+
+    Ok(Json(PublicInvitationResponse::from(&invitation)))
+}
+
+/// Generate an MFA secret for the invitee to scan
+///
+/// POST /api/v1/auth/invitations/mfa-setup
+///
+/// Nothing is stored; the secret and backup codes are sent back with the
+/// accept request together with a current TOTP code.
+pub async fn invitation_mfa_setup(
+    State(state): State<AppState>,
+    Json(request): Json<InvitationTokenRequest>,
+) -> Result<Json<MfaSetupResponse>> {
+    let invitation = InvitationService::new(state.pool.clone())
+        .find_open_by_token(&request.token)
+        .await?;
+
+    Ok(Json(generate_mfa_setup(&invitation.email)?))
+}
+
+/// Accept an invitation: create the account with password and MFA
+///
+/// POST /api/v1/auth/invitations/accept
+///
+/// Returns the created user. The invitee then signs in normally.
+pub async fn accept_invitation(
+    State(state): State<AppState>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Json(request): Json<AcceptInvitationRequest>,
+) -> Result<(StatusCode, Json<serde_json::Value>)> {
+    request
+        .validate()
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+    validate_password(&request.password).map_err(AppError::Validation)?;
+
+    if request.backup_codes.is_empty() {
+        return Err(AppError::Validation(
+            "Backup codes from the MFA setup step are required".to_string(),
+        ));
+    }
+
+    let service = InvitationService::new(state.pool.clone());
+
+    // Reject unknown/expired tokens before doing any expensive hashing
+    let invitation = service.find_open_by_token(&request.token).await?;
+
+    if !verify_totp_code(&request.mfa_secret, &request.mfa_code, &invitation.email)? {
+        return Err(AppError::Unauthorized(
+            "Invalid MFA code. Please try again.".to_string(),
+        ));
+    }
+
+    let password_hash = PasswordHasherUtil::hash_password(&request.password)?;
+    let hashed_backup_codes = request
+        .backup_codes
+        .iter()
+        .map(|code| PasswordHasherUtil::hash_password(code).map_err(AppError::from))
+        .collect::<Result<Vec<String>>>()?;
+
+    let (invitation, user) = service
+        .accept_invitation(
+            &request.token,
+            InvitationCredentials {
+                username: request.username.trim().to_string(),
+                password_hash,
+                mfa_secret: request.mfa_secret.clone(),
+                hashed_backup_codes,
+            },
+        )
+        .await?;
+
+    audit_invitation(
+        &state,
+        Some(user.id),
+        AuditAction::Update,
+        invitation.id,
+        serde_json::json!({
+            "action": "accept",
+            "user_id": user.id,
+            "username": user.username,
+        }),
+        &request_ctx,
+    )
+    .await;
+
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(invitation.invited_by),
+            action: AuditAction::Create,
+            entity_type: EntityType::User,
+            entity_id: Some(user.id.to_string()),
+            changes: Some(serde_json::json!({
+                "username": user.username,
+                "email": user.email,
+                "role": user.role.to_string(),
+                "invitation_id": invitation.id,
+                "mfa_enabled": user.mfa_enabled,
+            })),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+
+    tracing::info!("Invitation {} accepted by new user {}", invitation.id, user.id);
+
+    Ok((
+        StatusCode::CREATED,
+        Json(serde_json::json!({
+            "id": user.id,
+            "username": user.username,
+            "email": user.email,
+            "role": user.role,
+            "mfa_enabled": user.mfa_enabled,
+        })),
+    ))
+}
@@ -0,0 +1,538 @@
+/*!
+ * Lab Handlers
+ *
+ * Lab orders and LOINC-coded results on the patient record.
+ *
+ * Endpoints:
+ * - GET /api/v1/patients/:id/lab-orders - List a patient's lab orders
+ * - POST /api/v1/patients/:id/lab-orders - Place a lab order
+ * - GET /api/v1/patients/:id/lab-orders/:order_id - Get a lab order
+ * - PUT /api/v1/patients/:id/lab-orders/:order_id - Update a lab order
+ * - DELETE /api/v1/patients/:id/lab-orders/:order_id - Delete a lab order
+ * - POST /api/v1/patients/:id/lab-orders/:order_id/requisition - Generate the requisition PDF
+ * - GET /api/v1/patients/:id/lab-results - List a patient's lab results
+ * - POST /api/v1/patients/:id/lab-results - Record a lab result
+ * - GET /api/v1/patients/:id/lab-results/trend - Results of one analyte over time
+ * - GET /api/v1/patients/:id/lab-results/:result_id - Get a lab result
+ * - PUT /api/v1/patients/:id/lab-results/:result_id - Update a lab result
+ * - DELETE /api/v1/patients/:id/lab-results/:result_id - Delete a lab result
+ * - GET /api/v1/lab-tests - Orderable test catalog
+ */
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    handlers::auth::AppState,
+    models::{
+        AuditAction, AuditLog, AuthUser, CreateAuditLog, CreateLabOrderRequest,
+        CreateLabResultRequest, EntityType, LabCatalogQuery, LabCatalogTest, LabOrderResponse,
+        LabResultResponse, LabTrend, LabTrendQuery, ListLabOrdersQuery, ListLabResultsQuery,
+        RequestContext, UpdateLabOrderRequest, UpdateLabResultRequest, UserRole,
+    },
+    services::LabService,
+    utils::{AppError, EncryptionKey, Result},
+};
+
+#[cfg(feature = "pdf-export")]
+use crate::{
+    models::{GenerateLabRequisitionRequest, LabRequisitionResponse},
+    services::DocumentService,
+};
+#[cfg(feature = "pdf-export")]
+use std::path::PathBuf;
+
+#[cfg(feature = "rbac")]
+use tracing::warn;
+
+/// Check if user has permission to perform action on labs resource
+#[cfg(feature = "rbac")]
+async fn check_permission(state: &AppState, user_role: &UserRole, action: &str) -> Result<()> {
+    let has_permission = state
+        .enforcer
+        .enforce(user_role, "labs", action)
+        .await
+        .map_err(|e| {
+            warn!("RBAC enforcement error: {}", e);
+            AppError::Internal("Failed to check permissions".to_string())
+        })?;
+
+    if !has_permission {
+        return Err(AppError::Forbidden(format!(
+            "User does not have permission to {} labs",
+            action
+        )));
+    }
+
+    Ok(())
+}
+
+/// Fallback for non-RBAC builds - only checks role
+#[cfg(not(feature = "rbac"))]
+async fn check_permission(_state: &AppState, user_role: &UserRole, action: &str) -> Result<()> {
+    let allowed = match action {
+        "read" | "record_result" => matches!(
+            user_role,
+            UserRole::Admin | UserRole::Doctor | UserRole::Nurse
+        ),
+        _ => matches!(user_role, UserRole::Admin | UserRole::Doctor),
+    };
+
+    if !allowed {
+        return Err(AppError::Forbidden("Insufficient permissions".to_string()));
+    }
+    Ok(())
+}
+
+fn service_encryption_key(state: &AppState) -> Result<EncryptionKey> {
+    state
+        .encryption_key
+        .clone()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))
+}
+
+fn lab_service(state: &AppState) -> Result<LabService> {
+    Ok(LabService::new(state.pool.clone(), service_encryption_key(state)?))
+}
+
+/// Audit a lab order or result change; the payload never contains values,
+/// notes or clinical information
+async fn audit_lab(
+    state: &AppState,
+    auth_user: &AuthUser,
+    request_ctx: &RequestContext,
+    action: AuditAction,
+    entity_type: EntityType,
+    entity_id: Uuid,
+    changes: serde_json::Value,
+) {
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(auth_user.user_id),
+            action,
+            entity_type,
+            entity_id: Some(entity_id.to_string()),
+            changes: Some(changes),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+}
+
+/// List a patient's lab orders
+///
+/// GET /api/v1/patients/:id/lab-orders
+///
+/// **RBAC**: Requires 'read' permission on 'labs' resource
+pub async fn list_lab_orders(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(patient_id): Path<Uuid>,
+    Query(query): Query<ListLabOrdersQuery>,
+) -> Result<Json<Vec<LabOrderResponse>>> {
+    check_permission(&state, &auth_user.role, "read").await?;
+
+    let orders = lab_service(&state)?
+        .list_orders(patient_id, &query, auth_user.user_id)
+        .await?;
+
+    Ok(Json(orders))
+}
+
+/// Place a lab order
+///
+/// POST /api/v1/patients/:id/lab-orders
+///
+/// **RBAC**: Requires 'create' permission on 'labs' resource
+pub async fn create_lab_order(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(patient_id): Path<Uuid>,
+    Json(req): Json<CreateLabOrderRequest>,
+) -> Result<(StatusCode, Json<LabOrderResponse>)> {
+    check_permission(&state, &auth_user.role, "create").await?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let order = lab_service(&state)?
+        .create_order(patient_id, &req, auth_user.user_id)
+        .await?;
+
+    audit_lab(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Create,
+        EntityType::LabOrder,
+        order.id,
+        serde_json::json!({
+            "patient_id": patient_id,
+            "visit_id": order.visit_id,
+            "urgency": order.urgency,
+            "loinc_codes": order.tests.iter().map(|t| &t.loinc_code).collect::<Vec<_>>(),
+        }),
+    )
+    .await;
+
+    Ok((StatusCode::CREATED, Json(order)))
+}
+
+/// Get a lab order
+///
+/// GET /api/v1/patients/:id/lab-orders/:order_id
+///
+/// **RBAC**: Requires 'read' permission on 'labs' resource
+pub async fn get_lab_order(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path((patient_id, order_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<LabOrderResponse>> {
+    check_permission(&state, &auth_user.role, "read").await?;
+
+    let order = lab_service(&state)?
+        .get_order(patient_id, order_id, auth_user.user_id)
+        .await?;
+
+    Ok(Json(order))
+}
+
+/// Update a lab order
+///
+/// PUT /api/v1/patients/:id/lab-orders/:order_id
+///
+/// **RBAC**: Requires 'update' permission on 'labs' resource
+pub async fn update_lab_order(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path((patient_id, order_id)): Path<(Uuid, Uuid)>,
+    Json(req): Json<UpdateLabOrderRequest>,
+) -> Result<Json<LabOrderResponse>> {
+    check_permission(&state, &auth_user.role, "update").await?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let order = lab_service(&state)?
+        .update_order(patient_id, order_id, &req, auth_user.user_id)
+        .await?;
+
+    audit_lab(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Update,
+        EntityType::LabOrder,
+        order_id,
+        serde_json::json!({
+            "patient_id": patient_id,
+            "status": req.status,
+            "urgency": req.urgency,
+            "fasting": req.fasting,
+        }),
+    )
+    .await;
+
+    Ok(Json(order))
+}
+
+/// Delete a lab order
+///
+/// DELETE /api/v1/patients/:id/lab-orders/:order_id
+///
+/// The order is marked deleted; its results are kept.
+///
+/// **RBAC**: Requires 'delete' permission on 'labs' resource
+pub async fn delete_lab_order(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path((patient_id, order_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode> {
+    check_permission(&state, &auth_user.role, "delete").await?;
+
+    lab_service(&state)?
+        .delete_order(patient_id, order_id, auth_user.user_id)
+        .await?;
+
+    audit_lab(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Delete,
+        EntityType::LabOrder,
+        order_id,
+        serde_json::json!({ "patient_id": patient_id }),
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Generate the requisition PDF for a lab order
+///
+/// POST /api/v1/patients/:id/lab-orders/:order_id/requisition
+///
+/// Assigns the order's requisition number on first use (regenerating keeps
+/// it), renders the LAB_REQUEST template with the order's tests and a
+/// barcode of the number, and links the document to the order.
+///
+/// **RBAC**: Requires 'requisition' permission on 'labs' resource
+#[cfg(feature = "pdf-export")]
+pub async fn generate_lab_requisition(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path((patient_id, order_id)): Path<(Uuid, Uuid)>,
+    Json(req): Json<GenerateLabRequisitionRequest>,
+) -> Result<(StatusCode, Json<LabRequisitionResponse>)> {
+    check_permission(&state, &auth_user.role, "requisition").await?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let service = lab_service(&state)?;
+    let order = service
+        .prepare_requisition(patient_id, order_id, auth_user.user_id)
+        .await?;
+
+    let storage_path = PathBuf::from(
+        std::env::var("DOCUMENT_STORAGE_PATH").unwrap_or_else(|_| "./documents".to_string()),
+    );
+    let document = DocumentService::new(
+        state.pool.clone(),
+        service_encryption_key(&state)?,
+        storage_path,
+    )
+    .with_cache(state.reference_cache.clone())
+    .generate_lab_requisition(&order, &req, auth_user.user_id)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to generate requisition for lab order {}: {:?}", order_id, e);
+        AppError::Internal(format!("Failed to generate lab requisition: {:#}", e))
+    })?;
+
+    let order = service
+        .link_requisition(patient_id, order_id, document.id, auth_user.user_id)
+        .await?;
+
+    audit_lab(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Create,
+        EntityType::LabOrder,
+        order_id,
+        serde_json::json!({
+            "patient_id": patient_id,
+            "requisition_number": order.requisition_number,
+            "document_id": document.id,
+            "type": "requisition",
+        }),
+    )
+    .await;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(LabRequisitionResponse { order, document }),
+    ))
+}
+
+/// List a patient's lab results
+///
+/// GET /api/v1/patients/:id/lab-results
+///
+/// **RBAC**: Requires 'read' permission on 'labs' resource
+pub async fn list_lab_results(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(patient_id): Path<Uuid>,
+    Query(query): Query<ListLabResultsQuery>,
+) -> Result<Json<Vec<LabResultResponse>>> {
+    check_permission(&state, &auth_user.role, "read").await?;
+
+    let results = lab_service(&state)?
+        .list_results(patient_id, &query, auth_user.user_id)
+        .await?;
+
+    Ok(Json(results))
+}
+
+/// Record a lab result
+///
+/// POST /api/v1/patients/:id/lab-results
+///
+/// **RBAC**: Requires 'record_result' permission on 'labs' resource
+pub async fn create_lab_result(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(patient_id): Path<Uuid>,
+    Json(req): Json<CreateLabResultRequest>,
+) -> Result<(StatusCode, Json<LabResultResponse>)> {
+    check_permission(&state, &auth_user.role, "record_result").await?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let result = lab_service(&state)?
+        .create_result(patient_id, &req, auth_user.user_id)
+        .await?;
+
+    audit_lab(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Create,
+        EntityType::LabResult,
+        result.id,
+        serde_json::json!({
+            "patient_id": patient_id,
+            "order_id": result.order_id,
+            "visit_id": result.visit_id,
+            "loinc_code": result.loinc_code,
+            "abnormal_flag": result.abnormal_flag,
+        }),
+    )
+    .await;
+
+    Ok((StatusCode::CREATED, Json(result)))
+}
+
+/// Results of one analyte over time
+///
+/// GET /api/v1/patients/:id/lab-results/trend?loinc_code=2345-7
+///
+/// **RBAC**: Requires 'read' permission on 'labs' resource
+pub async fn get_lab_result_trend(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(patient_id): Path<Uuid>,
+    Query(query): Query<LabTrendQuery>,
+) -> Result<Json<LabTrend>> {
+    check_permission(&state, &auth_user.role, "read").await?;
+
+    let trend = lab_service(&state)?
+        .trend(patient_id, &query, auth_user.user_id)
+        .await?;
+
+    Ok(Json(trend))
+}
+
+/// Get a lab result
+///
+/// GET /api/v1/patients/:id/lab-results/:result_id
+///
+/// **RBAC**: Requires 'read' permission on 'labs' resource
+pub async fn get_lab_result(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path((patient_id, result_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<LabResultResponse>> {
+    check_permission(&state, &auth_user.role, "read").await?;
+
+    let result = lab_service(&state)?
+        .get_result(patient_id, result_id, auth_user.user_id)
+        .await?;
+
+    Ok(Json(result))
+}
+
+/// Update a lab result
+///
+/// PUT /api/v1/patients/:id/lab-results/:result_id
+///
+/// **RBAC**: Requires 'update' permission on 'labs' resource
+pub async fn update_lab_result(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path((patient_id, result_id)): Path<(Uuid, Uuid)>,
+    Json(req): Json<UpdateLabResultRequest>,
+) -> Result<Json<LabResultResponse>> {
+    check_permission(&state, &auth_user.role, "update").await?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let result = lab_service(&state)?
+        .update_result(patient_id, result_id, &req, auth_user.user_id)
+        .await?;
+
+    audit_lab(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Update,
+        EntityType::LabResult,
+        result_id,
+        serde_json::json!({
+            "patient_id": patient_id,
+            "value_changed": req.value.is_some(),
+            "abnormal_flag": result.abnormal_flag,
+        }),
+    )
+    .await;
+
+    Ok(Json(result))
+}
+
+/// Delete a lab result
+///
+/// DELETE /api/v1/patients/:id/lab-results/:result_id
+///
+/// The result is marked deleted and kept with the medical record.
+///
+/// **RBAC**: Requires 'delete' permission on 'labs' resource
+pub async fn delete_lab_result(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path((patient_id, result_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode> {
+    check_permission(&state, &auth_user.role, "delete").await?;
+
+    lab_service(&state)?
+        .delete_result(patient_id, result_id, auth_user.user_id)
+        .await?;
+
+    audit_lab(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Delete,
+        EntityType::LabResult,
+        result_id,
+        serde_json::json!({ "patient_id": patient_id }),
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Orderable test catalog
+///
+/// GET /api/v1/lab-tests?search=glu&category=CHIMICA%20CLINICA
+///
+/// **RBAC**: Requires 'read' permission on 'labs' resource
+pub async fn list_lab_catalog(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Query(query): Query<LabCatalogQuery>,
+) -> Result<Json<Vec<LabCatalogTest>>> {
+    check_permission(&state, &auth_user.role, "read").await?;
+
+    let tests = lab_service(&state)?.list_catalog(&query).await?;
+
+    Ok(Json(tests))
+}
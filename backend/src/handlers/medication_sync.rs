@@ -0,0 +1,96 @@
+/*!
+ * Medication Sync Handlers
+ *
+ * ADMIN-only import of the AIFA medication list and its run history.
+ *
+ * Endpoints:
+ * - POST /api/v1/medication-sync/run - Download and import the AIFA list now (dry run by default)
+ * - GET /api/v1/medication-sync/runs - Run history
+ * - GET /api/v1/medication-sync/runs/:id - One run with its diff
+ */
+
+use axum::{
+    extract::{Path, Query, State},
+    Extension, Json,
+};
+use uuid::Uuid;
+
+use crate::{
+    handlers::auth::AppState,
+    models::{
+        AuthUser, ListMedicationSyncRunsQuery, MedicationSyncRun, RunMedicationSyncRequest,
+        UserRole,
+    },
+    services::MedicationImportService,
+    utils::{AppError, Result},
+};
+
+/// Only administrators may sync the medication list
+fn require_admin(auth_user: &AuthUser) -> Result<()> {
+    if auth_user.role != UserRole::Admin {
+        return Err(AppError::Forbidden(
+            "Only administrators can sync the medication list".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Sync the AIFA medication list now
+///
+/// POST /api/v1/medication-sync/run
+///
+/// Downloads the lists from the `aifa_class_a_url` and `aifa_atc_url`
+/// settings. Dry run unless `dry_run` is false. A failed download or import
+/// is returned as a FAILED run. Each run is audited by the service.
+pub async fn run_medication_sync(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Json(req): Json<RunMedicationSyncRequest>,
+) -> Result<Json<MedicationSyncRun>> {
+    require_admin(&auth_user)?;
+
+    let run = MedicationImportService::new(state.pool.clone())
+        .with_cache(state.reference_cache.clone())
+        .sync_from_settings(
+            &state.settings_service,
+            req.dry_run,
+            Some(auth_user.user_id),
+        )
+        .await?;
+
+    Ok(Json(run))
+}
+
+/// List medication sync runs, newest first
+///
+/// GET /api/v1/medication-sync/runs
+pub async fn list_medication_sync_runs(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Query(query): Query<ListMedicationSyncRunsQuery>,
+) -> Result<Json<Vec<MedicationSyncRun>>> {
+    require_admin(&auth_user)?;
+
+    let runs = MedicationImportService::new(state.pool.clone())
+        .list_runs(&query)
+        .await?;
+
+    Ok(Json(runs))
+}
+
+/// Get a medication sync run with its diff
+///
+/// GET /api/v1/medication-sync/runs/:id
+pub async fn get_medication_sync_run(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(run_id): Path<Uuid>,
+) -> Result<Json<MedicationSyncRun>> {
+    require_admin(&auth_user)?;
+
+    let run = MedicationImportService::new(state.pool.clone())
+        .get_run(run_id)
+        .await?;
+
+    Ok(Json(run))
+}
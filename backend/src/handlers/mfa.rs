@@ -110,49 +110,12 @@ pub async fn mfa_setup_handler(
         return Err(AppError::Forbidden("Account is inactive".to_string()));
     }
 
-    // Generate TOTP secret
-    let secret = Secret::generate_secret();
-    let secret_base32 = secret.to_encoded().to_string();
-
-    // Create TOTP instance with issuer and account name
-    let account_name = format!("{}@docpat", user.username);
-    let issuer = "DocPat Medical";
-
-    let totp = TOTP::new(
-        Algorithm::SHA1,
-        6,                          // 6-digit codes
-        1,                          // 1 time step (30 seconds)
-        30,                         // 30 second time step
-        secret.to_bytes().map_err(|_| {
-            tracing::error!("Failed to generate TOTP secret");
-            AppError::Internal("Failed to generate MFA secret".to_string())
-        })?,
-        Some(issuer.to_string()),
-        account_name.clone(),
-    )
-    .map_err(|_| {
-        tracing::error!("Failed to create TOTP configuration");
-        AppError::Internal("Failed to create MFA configuration".to_string())
-    })?;
-
-    // Generate TOTP URI for QR code
-    let totp_uri = totp.get_url();
-
-    // Generate QR code
-    let qr_code = generate_qr_code(&totp_uri)?;
-
-    // Generate backup codes
-    let backup_codes = generate_backup_codes();
+    let setup = generate_mfa_setup(&user.username)?;
 
     // Log MFA setup attempt (but not the secret)
     tracing::info!("MFA setup generated for user: {}", user.id);
 
-    Ok(Json(MfaSetupResponse {
-        secret: secret_base32,
-        qr_code,
-        totp_uri,
-        backup_codes,
-    }))
+    Ok(Json(setup))
 }
 
 /// Enroll user in MFA after verifying setup code
@@ -202,31 +165,9 @@ pub async fn mfa_enroll_handler(
     }
 
     // Verify the provided code with the secret
-    let secret = Secret::Encoded(req.secret.clone())
-        .to_bytes()
-        .map_err(|_| {
-            tracing::error!("Invalid MFA secret format for user {}", user.id);
-            AppError::BadRequest("Invalid MFA secret".to_string())
-        })?;
-
-    let totp = TOTP::new(
-        Algorithm::SHA1,
-        6,
-        1,
-        30,
-        secret,
-        Some("DocPat Medical".to_string()),
-        user.username.clone(),
-    )
-    .map_err(|_| {
-        tracing::error!("Failed to create TOTP for user {}", user.id);
-        AppError::Internal("Failed to verify MFA code".to_string())
-    })?;
-
-    // Verify the code
-    let is_valid = totp.check_current(&req.code).map_err(|_| {
+    let is_valid = verify_totp_code(&req.secret, &req.code, &user.username).map_err(|e| {
         tracing::warn!("MFA verification failed for user {}", user.id);
-        AppError::Unauthorized("Invalid MFA code".to_string())
+        e
     })?;
 
     if !is_valid {
@@ -274,6 +215,77 @@ pub async fn mfa_enroll_handler(
     }))
 }
 
+/// Generate a fresh TOTP secret, QR code and backup codes for an account
+///
+/// Nothing is persisted; the caller confirms enrollment with a valid code.
+/// Shared by the authenticated MFA setup and the invitation acceptance flow.
+pub(crate) fn generate_mfa_setup(username: &str) -> Result<MfaSetupResponse> {
+    // Generate TOTP secret
+    let secret = Secret::generate_secret();
+    let secret_base32 = secret.to_encoded().to_string();
+
+    // Create TOTP instance with issuer and account name
+    let account_name = format!("{}@docpat", username);
+    let issuer = "DocPat Medical";
+
+    let totp = TOTP::new(
+        Algorithm::SHA1,
+        6,                          // 6-digit codes
+        1,                          // 1 time step (30 seconds)
+        30,                         // 30 second time step
+        secret.to_bytes().map_err(|_| {
+            tracing::error!("Failed to generate TOTP secret");
+            AppError::Internal("Failed to generate MFA secret".to_string())
+        })?,
+        Some(issuer.to_string()),
+        account_name.clone(),
+    )
+    .map_err(|_| {
+        tracing::error!("Failed to create TOTP configuration");
+        AppError::Internal("Failed to create MFA configuration".to_string())
+    })?;
+
+    // Generate TOTP URI for QR code
+    let totp_uri = totp.get_url();
+
+    // Generate QR code
+    let qr_code = generate_qr_code(&totp_uri)?;
+
+    // Generate backup codes
+    let backup_codes = generate_backup_codes();
+
+    Ok(MfaSetupResponse {
+        secret: secret_base32,
+        qr_code,
+        totp_uri,
+        backup_codes,
+    })
+}
+
+/// Check a TOTP code against a base32 secret from the setup step
+pub(crate) fn verify_totp_code(secret: &str, code: &str, username: &str) -> Result<bool> {
+    let secret = Secret::Encoded(secret.to_string())
+        .to_bytes()
+        .map_err(|_| AppError::BadRequest("Invalid MFA secret".to_string()))?;
+
+    let totp = TOTP::new(
+        Algorithm::SHA1,
+        6,
+        1,
+        30,
+        secret,
+        Some("DocPat Medical".to_string()),
+        username.to_string(),
+    )
+    .map_err(|_| {
+        tracing::error!("Failed to create TOTP configuration");
+        AppError::Internal("Failed to verify MFA code".to_string())
+    })?;
+
+    totp.check_current(code)
+        .map_err(|_| AppError::Unauthorized("Invalid MFA code".to_string()))
+}
+
 /// Generate QR code as base64-encoded PNG image
 ///
 /// # Arguments
@@ -320,7 +332,7 @@ fn generate_qr_code(data: &str) -> Result<String> {
 /// # Returns
 ///
 /// Vector of 10 backup codes
-fn generate_backup_codes() -> Vec<String> {
+pub(crate) fn generate_backup_codes() -> Vec<String> {
     use rand::Rng;
 
     let mut rng = rand::thread_rng();
@@ -4,29 +4,75 @@
  * Contains all HTTP request handlers for the API endpoints.
  */
 
+pub mod appointment_links;
 pub mod appointments;
 pub mod audit_logs;
 pub mod auth;
+pub mod batch;
+pub mod booking;
+pub mod branding;
+pub mod calculators;
+pub mod calendar_feeds;
+pub mod consents;
 pub mod drug_interactions;
 pub mod files;
 pub mod system_health;
+pub mod delegations;
 pub mod diagnoses;
+pub mod e_prescriptions;
 pub mod holidays;
+pub mod imaging;
+pub mod impersonation;
+pub mod labs;
+pub mod medication_sync;
 pub mod mfa;
 pub mod notifications;
+pub mod overbooking;
+pub mod patient_allergies;
+pub mod patient_attachments;
+pub mod patient_communications;
+pub mod patient_erasure;
+pub mod patient_exports;
+pub mod patient_merge;
+pub mod patient_messages;
+pub mod patient_photos;
+pub mod patient_portal;
+pub mod patient_cohorts;
+pub mod patient_problems;
 pub mod patients;
 pub mod prescriptions;
 pub mod prescription_templates;
+pub mod recalls;
+pub mod referrals;
 pub mod reports;
+pub mod resources;
+pub mod report_subscriptions;
+pub mod rate_limits;
+pub mod retention;
 pub mod settings;
+pub mod televisits;
+pub mod trash;
+pub mod visit_addenda;
+pub mod visit_attachments;
+pub mod visit_cosign;
+pub mod visit_surveys;
 pub mod visits;
 pub mod visit_templates;
 pub mod visit_versions;
+pub mod vitals;
+pub mod waiting_room;
+pub mod waitlist;
 pub mod working_hours;
 
 #[cfg(feature = "rbac")]
 pub mod users;
 
+#[cfg(feature = "rbac")]
+pub mod authorization;
+
+#[cfg(feature = "rbac")]
+pub mod invitations;
+
 #[cfg(feature = "pdf-export")]
 pub mod documents;
 
@@ -35,14 +81,19 @@ pub use appointments::{
     get_daily_schedule, get_monthly_schedule, get_weekly_schedule, list_appointments,
     update_appointment,
 };
-pub use auth::{login_handler, logout_handler, refresh_token_handler};
+pub use auth::{
+    captcha_config_handler, change_password_handler, introspect_handler, jwks_handler,
+    list_trusted_devices_handler, login_handler, logout_handler, refresh_token_handler,
+    revoke_all_trusted_devices_handler, revoke_token_handler, revoke_trusted_device_handler,
+};
 pub use diagnoses::{
     create_diagnosis, delete_diagnosis, get_diagnosis, get_patient_diagnoses,
     get_visit_diagnoses, search_icd10, update_diagnosis,
 };
 pub use mfa::{mfa_enroll_handler, mfa_setup_handler};
 pub use patients::{
-    create_patient, delete_patient, get_patient, get_statistics as get_patient_statistics,
+    autocomplete_patients, create_patient, delete_patient, get_patient, get_patient_chart,
+    get_statistics as get_patient_statistics,
     list_patients, reactivate_patient, search_patients, update_patient,
 };
 pub use prescriptions::{
@@ -53,7 +104,7 @@ pub use prescriptions::{
 };
 pub use visits::{
     create_visit, delete_visit, get_patient_visits, get_visit, get_visit_statistics, list_visits,
-    lock_visit, sign_visit, update_visit,
+    lock_visit, search_visit_notes, sign_visit, update_visit,
 };
 pub use visit_templates::{
     create_template as create_visit_template, delete_template as delete_visit_template,
@@ -70,8 +121,9 @@ pub use visit_versions::{
     get_visit_version, list_visit_versions, restore_visit_version,
 };
 pub use reports::{
-    export_report, get_appointment_report, get_dashboard_report, get_diagnosis_report,
-    get_patient_report, get_productivity_report, get_revenue_report,
+    export_quality_indicators, export_report, export_research_dataset, get_appointment_heatmap,
+    get_appointment_report, get_dashboard_report, get_diagnosis_report, get_patient_report,
+    get_productivity_report, get_quality_indicators, get_revenue_report,
 };
 pub use settings::{
     bulk_update_settings, get_setting, get_settings_by_group, list_groups, list_settings,
@@ -181,6 +181,10 @@ pub async fn create_notification(
         .create_notification(req.clone(), auth_user.user_id)
         .await
         .map_err(|e| {
+            let msg = e.to_string();
+            if msg.contains("MARKETING consent") || msg.contains("require a patient_id") {
+                return AppError::Validation(msg);
+            }
             tracing::error!("Failed to create notification: {}", e);
             AppError::Internal(format!("Failed to create notification: {}", e))
         })?;
@@ -0,0 +1,143 @@
+/*!
+ * Overbooking Policy Handlers
+ *
+ * Which providers' calendars may be overbooked, and how far. Booking over
+ * other appointments is asked for with `overbook` when creating or moving
+ * an appointment.
+ *
+ * Endpoints:
+ * - GET /api/v1/appointments/overbooking-policies - Providers with a policy
+ * - PUT /api/v1/appointments/overbooking-policies/:provider_id - Set a provider's policy
+ * - DELETE /api/v1/appointments/overbooking-policies/:provider_id - Remove a policy
+ */
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    handlers::{appointments::check_permission, auth::AppState},
+    models::{
+        AuditAction, AuditLog, CreateAuditLog, EntityType, OverbookingPolicy, RequestContext,
+        SetOverbookingPolicyRequest, UserRole,
+    },
+    services::OverbookingService,
+    utils::{AppError, Result},
+};
+
+/// Audit a change to a provider's overbooking policy
+async fn audit_policy(
+    state: &AppState,
+    user_id: Uuid,
+    request_ctx: &RequestContext,
+    provider_id: Uuid,
+    changes: serde_json::Value,
+) {
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(user_id),
+            action: AuditAction::Update,
+            entity_type: EntityType::User,
+            entity_id: Some(provider_id.to_string()),
+            changes: Some(changes),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+}
+
+/// Providers with an overbooking policy
+///
+/// GET /api/v1/appointments/overbooking-policies
+///
+/// **RBAC**: Requires 'manage_overbooking' permission on 'appointments' resource
+pub async fn list_overbooking_policies(
+    State(state): State<AppState>,
+    Extension(user_role): Extension<UserRole>,
+) -> Result<Json<Vec<OverbookingPolicy>>> {
+    check_permission(&state, &user_role, "manage_overbooking").await?;
+
+    let policies = OverbookingService::new(state.pool.clone())
+        .list_policies()
+        .await?;
+
+    Ok(Json(policies))
+}
+
+/// Set a provider's overbooking policy
+///
+/// PUT /api/v1/appointments/overbooking-policies/:provider_id
+///
+/// **RBAC**: Requires 'manage_overbooking' permission on 'appointments' resource
+pub async fn set_overbooking_policy(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(user_role): Extension<UserRole>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(provider_id): Path<Uuid>,
+    Json(request): Json<SetOverbookingPolicyRequest>,
+) -> Result<Json<OverbookingPolicy>> {
+    check_permission(&state, &user_role, "manage_overbooking").await?;
+
+    request
+        .validate()
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+
+    let policy = OverbookingService::new(state.pool.clone())
+        .set_policy(provider_id, &request, user_id)
+        .await?;
+
+    audit_policy(
+        &state,
+        user_id,
+        &request_ctx,
+        provider_id,
+        serde_json::json!({
+            "action": "overbooking_policy_set",
+            "max_parallel": policy.max_parallel,
+            "appointment_types": policy.appointment_types,
+            "start_time": policy.start_time,
+            "end_time": policy.end_time,
+        }),
+    )
+    .await;
+
+    Ok(Json(policy))
+}
+
+/// Remove a provider's overbooking policy
+///
+/// DELETE /api/v1/appointments/overbooking-policies/:provider_id
+///
+/// **RBAC**: Requires 'manage_overbooking' permission on 'appointments' resource
+pub async fn delete_overbooking_policy(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(user_role): Extension<UserRole>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(provider_id): Path<Uuid>,
+) -> Result<StatusCode> {
+    check_permission(&state, &user_role, "manage_overbooking").await?;
+
+    OverbookingService::new(state.pool.clone())
+        .remove_policy(provider_id)
+        .await?;
+
+    audit_policy(
+        &state,
+        user_id,
+        &request_ctx,
+        provider_id,
+        serde_json::json!({ "action": "overbooking_policy_removed" }),
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
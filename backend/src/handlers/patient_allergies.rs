@@ -0,0 +1,262 @@
+/*!
+ * Patient Allergy Handlers
+ *
+ * Structured allergies and intolerances on the patient record.
+ *
+ * Endpoints:
+ * - GET /api/v1/patients/:id/allergies - List a patient's allergies
+ * - POST /api/v1/patients/:id/allergies - Record an allergy
+ * - GET /api/v1/patients/:id/allergies/:allergy_id - Get an allergy
+ * - PUT /api/v1/patients/:id/allergies/:allergy_id - Update an allergy
+ * - DELETE /api/v1/patients/:id/allergies/:allergy_id - Delete an allergy
+ */
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    handlers::auth::AppState,
+    models::{
+        AuditAction, AuditLog, AuthUser, CreateAuditLog, CreatePatientAllergyRequest, EntityType,
+        ListPatientAllergiesQuery, PatientAllergyResponse, RequestContext,
+        UpdatePatientAllergyRequest, UserRole,
+    },
+    services::PatientAllergyService,
+    utils::{AppError, Result},
+};
+
+#[cfg(feature = "rbac")]
+use tracing::warn;
+
+/// Check if user has permission to perform action on allergies resource
+#[cfg(feature = "rbac")]
+async fn check_permission(state: &AppState, user_role: &UserRole, action: &str) -> Result<()> {
+    let has_permission = state
+        .enforcer
+        .enforce(user_role, "allergies", action)
+        .await
+        .map_err(|e| {
+            warn!("RBAC enforcement error: {}", e);
+            AppError::Internal("Failed to check permissions".to_string())
+        })?;
+
+    if !has_permission {
+        return Err(AppError::Forbidden(format!(
+            "User does not have permission to {} allergies",
+            action
+        )));
+    }
+
+    Ok(())
+}
+
+/// Fallback for non-RBAC builds - only checks role
+#[cfg(not(feature = "rbac"))]
+async fn check_permission(_state: &AppState, user_role: &UserRole, action: &str) -> Result<()> {
+    let allowed = match action {
+        "read" | "create" => matches!(
+            user_role,
+            UserRole::Admin | UserRole::Doctor | UserRole::Nurse
+        ),
+        _ => matches!(user_role, UserRole::Admin | UserRole::Doctor),
+    };
+
+    if !allowed {
+        return Err(AppError::Forbidden("Insufficient permissions".to_string()));
+    }
+    Ok(())
+}
+
+fn allergy_service(state: &AppState) -> Result<PatientAllergyService> {
+    let encryption_key = state
+        .encryption_key
+        .as_ref()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+
+    Ok(PatientAllergyService::new(
+        state.pool.clone(),
+        encryption_key.clone(),
+    ))
+}
+
+/// Audit an allergy change; the payload never contains the encrypted fields
+async fn audit_allergy(
+    state: &AppState,
+    auth_user: &AuthUser,
+    request_ctx: &RequestContext,
+    action: AuditAction,
+    allergy_id: Uuid,
+    changes: serde_json::Value,
+) {
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(auth_user.user_id),
+            action,
+            entity_type: EntityType::PatientAllergy,
+            entity_id: Some(allergy_id.to_string()),
+            changes: Some(changes),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+}
+
+/// List a patient's allergies
+///
+/// GET /api/v1/patients/:id/allergies
+///
+/// **RBAC**: Requires 'read' permission on 'allergies' resource
+pub async fn list_patient_allergies(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(patient_id): Path<Uuid>,
+    Query(query): Query<ListPatientAllergiesQuery>,
+) -> Result<Json<Vec<PatientAllergyResponse>>> {
+    check_permission(&state, &auth_user.role, "read").await?;
+
+    let allergies = allergy_service(&state)?
+        .list_allergies(patient_id, &query, auth_user.user_id)
+        .await?;
+
+    Ok(Json(allergies))
+}
+
+/// Record an allergy
+///
+/// POST /api/v1/patients/:id/allergies
+///
+/// **RBAC**: Requires 'create' permission on 'allergies' resource
+pub async fn create_patient_allergy(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(patient_id): Path<Uuid>,
+    Json(req): Json<CreatePatientAllergyRequest>,
+) -> Result<(StatusCode, Json<PatientAllergyResponse>)> {
+    check_permission(&state, &auth_user.role, "create").await?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let allergy = allergy_service(&state)?
+        .create_allergy(patient_id, &req, auth_user.user_id)
+        .await?;
+
+    audit_allergy(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Create,
+        allergy.id,
+        serde_json::json!({
+            "patient_id": patient_id,
+            "allergy_type": allergy.allergy_type,
+            "category": allergy.category,
+            "severity": allergy.severity,
+            "status": allergy.status,
+        }),
+    )
+    .await;
+
+    Ok((StatusCode::CREATED, Json(allergy)))
+}
+
+/// Get an allergy
+///
+/// GET /api/v1/patients/:id/allergies/:allergy_id
+///
+/// **RBAC**: Requires 'read' permission on 'allergies' resource
+pub async fn get_patient_allergy(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path((patient_id, allergy_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<PatientAllergyResponse>> {
+    check_permission(&state, &auth_user.role, "read").await?;
+
+    let allergy = allergy_service(&state)?
+        .get_allergy(patient_id, allergy_id, auth_user.user_id)
+        .await?;
+
+    Ok(Json(allergy))
+}
+
+/// Update an allergy
+///
+/// PUT /api/v1/patients/:id/allergies/:allergy_id
+///
+/// **RBAC**: Requires 'update' permission on 'allergies' resource
+pub async fn update_patient_allergy(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path((patient_id, allergy_id)): Path<(Uuid, Uuid)>,
+    Json(req): Json<UpdatePatientAllergyRequest>,
+) -> Result<Json<PatientAllergyResponse>> {
+    check_permission(&state, &auth_user.role, "update").await?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let allergy = allergy_service(&state)?
+        .update_allergy(patient_id, allergy_id, &req, auth_user.user_id)
+        .await?;
+
+    audit_allergy(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Update,
+        allergy_id,
+        serde_json::json!({
+            "patient_id": patient_id,
+            "allergy_type": req.allergy_type,
+            "category": req.category,
+            "severity": req.severity,
+            "status": req.status,
+            "onset_date": req.onset_date,
+        }),
+    )
+    .await;
+
+    Ok(Json(allergy))
+}
+
+/// Delete an allergy
+///
+/// DELETE /api/v1/patients/:id/allergies/:allergy_id
+///
+/// The allergy is marked deleted and kept with the medical record.
+///
+/// **RBAC**: Requires 'delete' permission on 'allergies' resource
+pub async fn delete_patient_allergy(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path((patient_id, allergy_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode> {
+    check_permission(&state, &auth_user.role, "delete").await?;
+
+    allergy_service(&state)?
+        .delete_allergy(patient_id, allergy_id, auth_user.user_id)
+        .await?;
+
+    audit_allergy(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Delete,
+        allergy_id,
+        serde_json::json!({ "patient_id": patient_id }),
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,437 @@
+/*!
+ * Patient Attachment Handlers
+ *
+ * External documents (lab reports, referral letters, scans) on the patient
+ * record, encrypted at rest. Every download is recorded in the audit log.
+ *
+ * Endpoints:
+ * - GET /api/v1/patients/:id/attachments - List a patient's attachments
+ * - POST /api/v1/patients/:id/attachments - Upload an attachment (multipart)
+ * - GET /api/v1/patients/:id/attachments/:attachment_id - Get attachment metadata
+ * - PUT /api/v1/patients/:id/attachments/:attachment_id - Update attachment metadata
+ * - DELETE /api/v1/patients/:id/attachments/:attachment_id - Delete an attachment
+ * - GET /api/v1/patients/:id/attachments/:attachment_id/download - Download the file
+ * - POST /api/v1/patients/:id/attachments/:attachment_id/ocr - Run OCR again
+ */
+
+use axum::{
+    extract::{Multipart, Path, Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::IntoResponse,
+    Extension, Json,
+};
+use chrono::NaiveDate;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    handlers::auth::AppState,
+    models::{
+        uploaded_file::MAX_FILE_SIZE, AttachmentCategory, AuditAction, AuditLog, AuthUser,
+        CreateAuditLog, CreatePatientAttachmentRequest, EntityType, ListPatientAttachmentsQuery,
+        PatientAttachmentResponse, RequestContext, UpdatePatientAttachmentRequest, UserRole,
+    },
+    services::PatientAttachmentService,
+    utils::{AppError, Result},
+};
+
+#[cfg(feature = "rbac")]
+use tracing::warn;
+
+/// Check if user has permission to perform action on attachments resource
+#[cfg(feature = "rbac")]
+async fn check_permission(state: &AppState, user_role: &UserRole, action: &str) -> Result<()> {
+    let has_permission = state
+        .enforcer
+        .enforce(user_role, "attachments", action)
+        .await
+        .map_err(|e| {
+            warn!("RBAC enforcement error: {}", e);
+            AppError::Internal("Failed to check permissions".to_string())
+        })?;
+
+    if !has_permission {
+        return Err(AppError::Forbidden(format!(
+            "User does not have permission to {} attachments",
+            action
+        )));
+    }
+
+    Ok(())
+}
+
+/// Fallback for non-RBAC builds - only checks role
+#[cfg(not(feature = "rbac"))]
+async fn check_permission(_state: &AppState, user_role: &UserRole, action: &str) -> Result<()> {
+    let allowed = match action {
+        "read" | "create" => matches!(
+            user_role,
+            UserRole::Admin | UserRole::Doctor | UserRole::Nurse
+        ),
+        _ => matches!(user_role, UserRole::Admin | UserRole::Doctor),
+    };
+
+    if !allowed {
+        return Err(AppError::Forbidden("Insufficient permissions".to_string()));
+    }
+    Ok(())
+}
+
+fn attachment_service(state: &AppState) -> Result<PatientAttachmentService> {
+    let encryption_key = state
+        .encryption_key
+        .as_ref()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+
+    Ok(PatientAttachmentService::new(
+        state.pool.clone(),
+        encryption_key.clone(),
+    ))
+}
+
+async fn audit_attachment(
+    state: &AppState,
+    auth_user: &AuthUser,
+    request_ctx: &RequestContext,
+    action: AuditAction,
+    attachment_id: Uuid,
+    changes: serde_json::Value,
+) {
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(auth_user.user_id),
+            action,
+            entity_type: EntityType::PatientAttachment,
+            entity_id: Some(attachment_id.to_string()),
+            changes: Some(changes),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+}
+
+/// List a patient's attachments
+///
+/// GET /api/v1/patients/:id/attachments
+///
+/// `q` searches the title, description and recognized text.
+///
+/// **RBAC**: Requires 'read' permission on 'attachments' resource
+pub async fn list_patient_attachments(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(patient_id): Path<Uuid>,
+    Query(query): Query<ListPatientAttachmentsQuery>,
+) -> Result<Json<Vec<PatientAttachmentResponse>>> {
+    check_permission(&state, &auth_user.role, "read").await?;
+
+    let attachments = attachment_service(&state)?
+        .list_attachments(patient_id, &query, auth_user.user_id)
+        .await?;
+
+    Ok(Json(attachments))
+}
+
+/// Upload an attachment
+///
+/// POST /api/v1/patients/:id/attachments
+///
+/// Multipart form: `file` (PDF, JPEG or PNG, at most 10 MB), `category`,
+/// `title`, and optionally `description`, `document_date` (YYYY-MM-DD) and
+/// `visit_id`.
+///
+/// **RBAC**: Requires 'create' permission on 'attachments' resource
+pub async fn create_patient_attachment(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(patient_id): Path<Uuid>,
+    mut multipart: Multipart,
+) -> Result<(StatusCode, Json<PatientAttachmentResponse>)> {
+    check_permission(&state, &auth_user.role, "create").await?;
+
+    let mut file: Option<(Vec<u8>, String)> = None;
+    let mut category = None;
+    let mut title = None;
+    let mut description = None;
+    let mut document_date = None;
+    let mut visit_id = None;
+
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|_| AppError::BadRequest("Failed to parse multipart form".to_string()))?
+    {
+        let name = field.name().unwrap_or("").to_string();
+        if name == "file" {
+            let filename = field.file_name().unwrap_or("attachment").to_string();
+            let content = field
+                .bytes()
+                .await
+                .map_err(|_| AppError::BadRequest("Failed to read file".to_string()))?;
+            if content.len() > MAX_FILE_SIZE {
+                return Err(AppError::Validation(format!(
+                    "File exceeds maximum size of {} MB",
+                    MAX_FILE_SIZE / 1024 / 1024
+                )));
+            }
+            file = Some((content.to_vec(), filename));
+            continue;
+        }
+
+        let value = field
+            .text()
+            .await
+            .map_err(|_| AppError::BadRequest(format!("Failed to read field '{}'", name)))?;
+        let value = value.trim().to_string();
+        if value.is_empty() {
+            continue;
+        }
+
+        match name.as_str() {
+            "category" => {
+                category = Some(AttachmentCategory::from_str(&value).ok_or_else(|| {
+                    AppError::Validation(format!("Unknown attachment category '{}'", value))
+                })?)
+            }
+            "title" => title = Some(value),
+            "description" => description = Some(value),
+            "document_date" => {
+                document_date =
+                    Some(NaiveDate::parse_from_str(&value, "%Y-%m-%d").map_err(|_| {
+                        AppError::Validation("document_date must be YYYY-MM-DD".to_string())
+                    })?)
+            }
+            "visit_id" => {
+                visit_id = Some(
+                    Uuid::parse_str(&value)
+                        .map_err(|_| AppError::Validation("Invalid visit_id".to_string()))?,
+                )
+            }
+            _ => {}
+        }
+    }
+
+    let (content, filename) =
+        file.ok_or_else(|| AppError::BadRequest("No file provided".to_string()))?;
+    let req = CreatePatientAttachmentRequest {
+        category: category
+            .ok_or_else(|| AppError::Validation("category is required".to_string()))?,
+        title: title.ok_or_else(|| AppError::Validation("title is required".to_string()))?,
+        description,
+        document_date,
+        visit_id,
+    };
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let attachment = attachment_service(&state)?
+        .create_attachment(patient_id, &content, &filename, &req, auth_user.user_id)
+        .await?;
+
+    audit_attachment(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Create,
+        attachment.id,
+        serde_json::json!({
+            "patient_id": patient_id,
+            "visit_id": attachment.visit_id,
+            "category": attachment.category,
+            "mime_type": attachment.mime_type,
+            "file_size_bytes": attachment.file_size_bytes,
+        }),
+    )
+    .await;
+
+    Ok((StatusCode::CREATED, Json(attachment)))
+}
+
+/// Get attachment metadata
+///
+/// GET /api/v1/patients/:id/attachments/:attachment_id
+///
+/// Includes the recognized text once OCR has completed.
+///
+/// **RBAC**: Requires 'read' permission on 'attachments' resource
+pub async fn get_patient_attachment(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path((patient_id, attachment_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<PatientAttachmentResponse>> {
+    check_permission(&state, &auth_user.role, "read").await?;
+
+    let attachment = attachment_service(&state)?
+        .get_attachment(patient_id, attachment_id, auth_user.user_id)
+        .await?;
+
+    Ok(Json(attachment))
+}
+
+/// Download an attachment
+///
+/// GET /api/v1/patients/:id/attachments/:attachment_id/download
+///
+/// Every download is recorded in the audit log.
+///
+/// **RBAC**: Requires 'read' permission on 'attachments' resource
+pub async fn download_patient_attachment(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path((patient_id, attachment_id)): Path<(Uuid, Uuid)>,
+) -> Result<impl IntoResponse> {
+    check_permission(&state, &auth_user.role, "read").await?;
+
+    let (attachment, content) = attachment_service(&state)?
+        .download_attachment(patient_id, attachment_id, auth_user.user_id)
+        .await?;
+
+    audit_attachment(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Read,
+        attachment_id,
+        serde_json::json!({
+            "type": "patient_attachment_download",
+            "patient_id": patient_id,
+        }),
+    )
+    .await;
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_str(&attachment.mime_type)
+            .unwrap_or(HeaderValue::from_static("application/octet-stream")),
+    );
+    let disposition = format!(
+        "attachment; filename=\"{}\"",
+        attachment.original_filename.replace('"', "\\\"")
+    );
+    headers.insert(
+        header::CONTENT_DISPOSITION,
+        HeaderValue::from_str(&disposition).unwrap_or(HeaderValue::from_static("attachment")),
+    );
+    // Patient data: never cached
+    headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    headers.insert(
+        header::X_CONTENT_TYPE_OPTIONS,
+        HeaderValue::from_static("nosniff"),
+    );
+
+    Ok((StatusCode::OK, headers, content))
+}
+
+/// Update attachment metadata
+///
+/// PUT /api/v1/patients/:id/attachments/:attachment_id
+///
+/// **RBAC**: Requires 'update' permission on 'attachments' resource
+pub async fn update_patient_attachment(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path((patient_id, attachment_id)): Path<(Uuid, Uuid)>,
+    Json(req): Json<UpdatePatientAttachmentRequest>,
+) -> Result<Json<PatientAttachmentResponse>> {
+    check_permission(&state, &auth_user.role, "update").await?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let attachment = attachment_service(&state)?
+        .update_attachment(patient_id, attachment_id, &req, auth_user.user_id)
+        .await?;
+
+    audit_attachment(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Update,
+        attachment_id,
+        serde_json::json!({
+            "patient_id": patient_id,
+            "category": req.category,
+            "title": req.title,
+            "document_date": req.document_date,
+            "visit_id": req.visit_id,
+        }),
+    )
+    .await;
+
+    Ok(Json(attachment))
+}
+
+/// Delete an attachment
+///
+/// DELETE /api/v1/patients/:id/attachments/:attachment_id
+///
+/// The attachment is marked deleted; the encrypted file is kept with the
+/// medical record.
+///
+/// **RBAC**: Requires 'delete' permission on 'attachments' resource
+pub async fn delete_patient_attachment(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path((patient_id, attachment_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode> {
+    check_permission(&state, &auth_user.role, "delete").await?;
+
+    attachment_service(&state)?
+        .delete_attachment(patient_id, attachment_id, auth_user.user_id)
+        .await?;
+
+    audit_attachment(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Delete,
+        attachment_id,
+        serde_json::json!({ "patient_id": patient_id }),
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Queue an attachment for OCR again
+///
+/// POST /api/v1/patients/:id/attachments/:attachment_id/ocr
+///
+/// Clears the previous result, e.g. after a failure or an engine change.
+///
+/// **RBAC**: Requires 'update' permission on 'attachments' resource
+pub async fn requeue_patient_attachment_ocr(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path((patient_id, attachment_id)): Path<(Uuid, Uuid)>,
+) -> Result<(StatusCode, Json<PatientAttachmentResponse>)> {
+    check_permission(&state, &auth_user.role, "update").await?;
+
+    let attachment = attachment_service(&state)?
+        .requeue_ocr(patient_id, attachment_id, auth_user.user_id)
+        .await?;
+
+    audit_attachment(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Update,
+        attachment_id,
+        serde_json::json!({
+            "type": "patient_attachment_ocr_requeue",
+            "patient_id": patient_id,
+        }),
+    )
+    .await;
+
+    Ok((StatusCode::ACCEPTED, Json(attachment)))
+}
@@ -0,0 +1,402 @@
+/*!
+ * Patient Cohort Handlers
+ *
+ * Cohort builder: select patients by age range, diagnoses, active
+ * medications and last visit, save the criteria as named cohorts, and use a
+ * cohort for bulk document generation or a notification campaign. Cohorts
+ * list patient data, so they are limited to ADMIN and DOCTOR; doctors manage
+ * their own cohorts, administrators everyone's.
+ *
+ * Endpoints:
+ * - POST /api/v1/cohorts/preview - Preview criteria without saving them
+ * - GET /api/v1/cohorts - List saved cohorts
+ * - POST /api/v1/cohorts - Save a cohort
+ * - GET /api/v1/cohorts/:id - Get a cohort
+ * - PUT /api/v1/cohorts/:id - Update a cohort
+ * - DELETE /api/v1/cohorts/:id - Delete a cohort
+ * - GET /api/v1/cohorts/:id/preview - Preview a saved cohort
+ * - POST /api/v1/cohorts/:id/documents - Generate a document for each patient
+ * - POST /api/v1/cohorts/:id/notifications - Queue a notification campaign
+ */
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use uuid::Uuid;
+use validator::Validate;
+
+#[cfg(feature = "pdf-export")]
+use crate::models::{BulkGenerateRequest, BulkGenerateResult, CohortDocumentsRequest};
+#[cfg(feature = "pdf-export")]
+use crate::services::DocumentService;
+use crate::{
+    handlers::auth::AppState,
+    models::{
+        AuditAction, AuditLog, AuthUser, CohortCriteria, CohortNotificationRequest,
+        CohortNotificationResult, CohortPreview, CreateAuditLog, CreatePatientCohortRequest,
+        EntityType, PatientCohortResponse, RequestContext, UpdatePatientCohortRequest, UserRole,
+    },
+    services::{NotificationService, PatientCohortService},
+    utils::{AppError, Result},
+};
+
+/// Most patients of a cohort documents can be generated for, as for bulk
+/// generation
+#[cfg(feature = "pdf-export")]
+const MAX_BULK_DOCUMENTS: usize = 100;
+
+/// Only administrators and doctors may build cohorts; returns whether the
+/// user is an administrator
+fn require_cohort_access(auth_user: &AuthUser) -> Result<bool> {
+    match auth_user.role {
+        UserRole::Admin => Ok(true),
+        UserRole::Doctor => Ok(false),
+        _ => Err(AppError::Forbidden(
+            "Only administrators and doctors can build patient cohorts".to_string(),
+        )),
+    }
+}
+
+fn cohort_service(state: &AppState) -> Result<PatientCohortService> {
+    let encryption_key = state
+        .encryption_key
+        .clone()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+
+    Ok(PatientCohortService::new(
+        state.pool.clone(),
+        encryption_key,
+    ))
+}
+
+/// Audit an operation on a cohort
+async fn audit(
+    state: &AppState,
+    auth_user: &AuthUser,
+    request_ctx: &RequestContext,
+    action: AuditAction,
+    cohort_id: Option<Uuid>,
+    changes: Option<serde_json::Value>,
+) {
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(auth_user.user_id),
+            action,
+            entity_type: EntityType::PatientCohort,
+            entity_id: cohort_id.map(|id| id.to_string()),
+            changes,
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+}
+
+/// Preview criteria without saving them
+///
+/// POST /api/v1/cohorts/preview
+pub async fn preview_cohort_criteria(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Json(criteria): Json<CohortCriteria>,
+) -> Result<Json<CohortPreview>> {
+    require_cohort_access(&auth_user)?;
+
+    criteria
+        .validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let preview = cohort_service(&state)?
+        .preview(&criteria, auth_user.user_id)
+        .await?;
+
+    audit(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Search,
+        None,
+        Some(serde_json::json!({ "criteria": criteria, "count": preview.count })),
+    )
+    .await;
+
+    Ok(Json(preview))
+}
+
+/// List saved cohorts
+///
+/// GET /api/v1/cohorts
+pub async fn list_cohorts(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<Vec<PatientCohortResponse>>> {
+    let is_admin = require_cohort_access(&auth_user)?;
+
+    let cohorts = cohort_service(&state)?
+        .list(auth_user.user_id, is_admin)
+        .await?;
+
+    Ok(Json(cohorts))
+}
+
+/// Save a cohort owned by the caller
+///
+/// POST /api/v1/cohorts
+pub async fn create_cohort(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Json(req): Json<CreatePatientCohortRequest>,
+) -> Result<(StatusCode, Json<PatientCohortResponse>)> {
+    require_cohort_access(&auth_user)?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let cohort = cohort_service(&state)?
+        .create(&req, auth_user.user_id)
+        .await?;
+
+    audit(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Create,
+        Some(cohort.id),
+        Some(serde_json::json!({ "name": cohort.name, "criteria": cohort.criteria })),
+    )
+    .await;
+
+    Ok((StatusCode::CREATED, Json(cohort)))
+}
+
+/// Get a saved cohort
+///
+/// GET /api/v1/cohorts/:id
+pub async fn get_cohort(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(cohort_id): Path<Uuid>,
+) -> Result<Json<PatientCohortResponse>> {
+    let is_admin = require_cohort_access(&auth_user)?;
+
+    let cohort = cohort_service(&state)?
+        .get(cohort_id, auth_user.user_id, is_admin)
+        .await?;
+
+    Ok(Json(cohort))
+}
+
+/// Update a saved cohort
+///
+/// PUT /api/v1/cohorts/:id
+pub async fn update_cohort(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(cohort_id): Path<Uuid>,
+    Json(req): Json<UpdatePatientCohortRequest>,
+) -> Result<Json<PatientCohortResponse>> {
+    let is_admin = require_cohort_access(&auth_user)?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let cohort = cohort_service(&state)?
+        .update(cohort_id, &req, auth_user.user_id, is_admin)
+        .await?;
+
+    audit(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Update,
+        Some(cohort.id),
+        Some(serde_json::json!({ "name": cohort.name, "criteria": cohort.criteria })),
+    )
+    .await;
+
+    Ok(Json(cohort))
+}
+
+/// Delete a saved cohort
+///
+/// DELETE /api/v1/cohorts/:id
+pub async fn delete_cohort(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(cohort_id): Path<Uuid>,
+) -> Result<StatusCode> {
+    let is_admin = require_cohort_access(&auth_user)?;
+
+    cohort_service(&state)?
+        .delete(cohort_id, auth_user.user_id, is_admin)
+        .await?;
+
+    audit(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Delete,
+        Some(cohort_id),
+        None,
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Preview a saved cohort with the current patient records
+///
+/// GET /api/v1/cohorts/:id/preview
+pub async fn preview_cohort(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(cohort_id): Path<Uuid>,
+) -> Result<Json<CohortPreview>> {
+    let is_admin = require_cohort_access(&auth_user)?;
+
+    let service = cohort_service(&state)?;
+    let cohort = service.get(cohort_id, auth_user.user_id, is_admin).await?;
+    let preview = service.preview(&cohort.criteria, auth_user.user_id).await?;
+
+    audit(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Search,
+        Some(cohort_id),
+        Some(serde_json::json!({ "count": preview.count })),
+    )
+    .await;
+
+    Ok(Json(preview))
+}
+
+/// Generate a document from a template for each patient of a cohort
+///
+/// POST /api/v1/cohorts/:id/documents
+///
+/// Limited to 100 patients, like bulk generation. Failures for single
+/// patients are reported in the result.
+#[cfg(feature = "pdf-export")]
+pub async fn generate_cohort_documents(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(cohort_id): Path<Uuid>,
+    Json(req): Json<CohortDocumentsRequest>,
+) -> Result<Json<BulkGenerateResult>> {
+    let is_admin = require_cohort_access(&auth_user)?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let service = cohort_service(&state)?;
+    let cohort = service.get(cohort_id, auth_user.user_id, is_admin).await?;
+    let patient_ids = service
+        .patient_ids(&cohort.criteria, auth_user.user_id)
+        .await?;
+
+    if patient_ids.is_empty() || patient_ids.len() > MAX_BULK_DOCUMENTS {
+        return Err(AppError::Validation(format!(
+            "The cohort has {} patients; documents can be generated for 1-{} patients",
+            patient_ids.len(),
+            MAX_BULK_DOCUMENTS
+        )));
+    }
+    let template_id = req.template_id;
+    let bulk_request = BulkGenerateRequest {
+        template_id,
+        patient_ids,
+        title_prefix: req.title_prefix,
+        common_data: req.common_data,
+    };
+
+    let storage_path = std::path::PathBuf::from(
+        std::env::var("DOCUMENT_STORAGE_PATH").unwrap_or_else(|_| "./documents".to_string()),
+    );
+    let encryption_key = state
+        .encryption_key
+        .clone()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+    let result = DocumentService::new(state.pool.clone(), encryption_key, storage_path)
+        .with_cache(state.reference_cache.clone())
+        .bulk_generate(bulk_request, auth_user.user_id)
+        .await;
+
+    audit(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Create,
+        Some(cohort_id),
+        Some(serde_json::json!({
+            "type": "bulk_documents",
+            "template_id": template_id,
+            "documents": result.successful,
+            "failed": result.total_failed,
+        })),
+    )
+    .await;
+
+    Ok(Json(result))
+}
+
+/// Queue a notification for each patient of a cohort
+///
+/// POST /api/v1/cohorts/:id/notifications
+pub async fn send_cohort_notifications(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(cohort_id): Path<Uuid>,
+    Json(req): Json<CohortNotificationRequest>,
+) -> Result<Json<CohortNotificationResult>> {
+    let is_admin = require_cohort_access(&auth_user)?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let email_service = state
+        .email_service
+        .clone()
+        .ok_or_else(|| AppError::Internal("Email service not configured".to_string()))?;
+    let notification_service = NotificationService::new(state.pool.clone(), email_service);
+
+    let service = cohort_service(&state)?;
+    let cohort = service.get(cohort_id, auth_user.user_id, is_admin).await?;
+    let result = service
+        .queue_notifications(
+            &cohort.criteria,
+            &req,
+            &notification_service,
+            auth_user.user_id,
+        )
+        .await?;
+
+    audit(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Create,
+        Some(cohort_id),
+        Some(serde_json::json!({
+            "type": "notification_campaign",
+            "notification_type": req.notification_type,
+            "subject": req.subject,
+            "queued": result.queued,
+            "skipped": result.skipped.len(),
+        })),
+    )
+    .await;
+
+    Ok(Json(result))
+}
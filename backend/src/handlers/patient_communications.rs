@@ -0,0 +1,222 @@
+/*!
+ * Patient Communication Handlers
+ *
+ * Per-patient communication log. Sent notifications and delivered documents
+ * are logged automatically; staff record other contacts and annotate
+ * entries.
+ *
+ * Endpoints:
+ * - GET /api/v1/patients/:id/communications - List a patient's communication log
+ * - POST /api/v1/patients/:id/communications - Record a contact
+ * - GET /api/v1/patients/:id/communications/:communication_id - Get a log entry
+ * - POST /api/v1/patients/:id/communications/:communication_id/annotations - Annotate an entry
+ */
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    handlers::auth::AppState,
+    models::{
+        AuditAction, AuditLog, AuthUser, CommunicationAnnotationResponse, CreateAuditLog,
+        CreateCommunicationAnnotationRequest, CreatePatientCommunicationRequest, EntityType,
+        ListPatientCommunicationsQuery, ListPatientCommunicationsResponse,
+        PatientCommunicationResponse, RequestContext, UserRole,
+    },
+    services::PatientCommunicationService,
+    utils::{AppError, Result},
+};
+
+#[cfg(feature = "rbac")]
+use tracing::warn;
+
+/// Check if user has permission to perform action on communications resource
+#[cfg(feature = "rbac")]
+async fn check_permission(state: &AppState, user_role: &UserRole, action: &str) -> Result<()> {
+    let has_permission = state
+        .enforcer
+        .enforce(user_role, "communications", action)
+        .await
+        .map_err(|e| {
+            warn!("RBAC enforcement error: {}", e);
+            AppError::Internal("Failed to check permissions".to_string())
+        })?;
+
+    if !has_permission {
+        return Err(AppError::Forbidden(format!(
+            "User does not have permission to {} communications",
+            action
+        )));
+    }
+
+    Ok(())
+}
+
+/// Fallback for non-RBAC builds - only checks role
+#[cfg(not(feature = "rbac"))]
+async fn check_permission(_state: &AppState, user_role: &UserRole, _action: &str) -> Result<()> {
+    if !matches!(
+        user_role,
+        UserRole::Admin | UserRole::Doctor | UserRole::Nurse | UserRole::Receptionist
+    ) {
+        return Err(AppError::Forbidden("Insufficient permissions".to_string()));
+    }
+    Ok(())
+}
+
+fn communication_service(state: &AppState) -> Result<PatientCommunicationService> {
+    let encryption_key = state
+        .encryption_key
+        .as_ref()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+
+    Ok(PatientCommunicationService::new(
+        state.pool.clone(),
+        encryption_key.clone(),
+    ))
+}
+
+/// Audit a log change; the payload never contains notes or annotations
+async fn audit_communication(
+    state: &AppState,
+    auth_user: &AuthUser,
+    request_ctx: &RequestContext,
+    action: AuditAction,
+    communication_id: Uuid,
+    changes: serde_json::Value,
+) {
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(auth_user.user_id),
+            action,
+            entity_type: EntityType::PatientCommunication,
+            entity_id: Some(communication_id.to_string()),
+            changes: Some(changes),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+}
+
+/// List a patient's communication log
+///
+/// GET /api/v1/patients/:id/communications
+///
+/// **RBAC**: Requires 'read' permission on 'communications' resource
+pub async fn list_patient_communications(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(patient_id): Path<Uuid>,
+    Query(query): Query<ListPatientCommunicationsQuery>,
+) -> Result<Json<ListPatientCommunicationsResponse>> {
+    check_permission(&state, &auth_user.role, "read").await?;
+
+    let communications = communication_service(&state)?
+        .list(patient_id, &query, auth_user.user_id)
+        .await?;
+
+    Ok(Json(communications))
+}
+
+/// Record a contact
+///
+/// POST /api/v1/patients/:id/communications
+///
+/// **RBAC**: Requires 'create' permission on 'communications' resource
+pub async fn create_patient_communication(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(patient_id): Path<Uuid>,
+    Json(req): Json<CreatePatientCommunicationRequest>,
+) -> Result<(StatusCode, Json<PatientCommunicationResponse>)> {
+    check_permission(&state, &auth_user.role, "create").await?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let communication = communication_service(&state)?
+        .create(patient_id, &req, auth_user.user_id)
+        .await?;
+
+    audit_communication(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Create,
+        communication.id,
+        serde_json::json!({
+            "patient_id": patient_id,
+            "channel": communication.channel,
+            "direction": communication.direction,
+            "occurred_at": communication.occurred_at,
+        }),
+    )
+    .await;
+
+    Ok((StatusCode::CREATED, Json(communication)))
+}
+
+/// Get a log entry with its annotations
+///
+/// GET /api/v1/patients/:id/communications/:communication_id
+///
+/// **RBAC**: Requires 'read' permission on 'communications' resource
+pub async fn get_patient_communication(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path((patient_id, communication_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<PatientCommunicationResponse>> {
+    check_permission(&state, &auth_user.role, "read").await?;
+
+    let communication = communication_service(&state)?
+        .get(patient_id, communication_id, auth_user.user_id)
+        .await?;
+
+    Ok(Json(communication))
+}
+
+/// Annotate a log entry
+///
+/// POST /api/v1/patients/:id/communications/:communication_id/annotations
+///
+/// **RBAC**: Requires 'annotate' permission on 'communications' resource
+pub async fn annotate_patient_communication(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path((patient_id, communication_id)): Path<(Uuid, Uuid)>,
+    Json(req): Json<CreateCommunicationAnnotationRequest>,
+) -> Result<(StatusCode, Json<CommunicationAnnotationResponse>)> {
+    check_permission(&state, &auth_user.role, "annotate").await?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let annotation = communication_service(&state)?
+        .annotate(patient_id, communication_id, &req, auth_user.user_id)
+        .await?;
+
+    audit_communication(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Update,
+        communication_id,
+        serde_json::json!({
+            "patient_id": patient_id,
+            "annotation_id": annotation.id,
+        }),
+    )
+    .await;
+
+    Ok((StatusCode::CREATED, Json(annotation)))
+}
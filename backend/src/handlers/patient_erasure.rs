@@ -0,0 +1,216 @@
+/*!
+ * Patient Erasure Handlers
+ *
+ * GDPR right to erasure with four-eyes review. One ADMIN files a request,
+ * a different ADMIN approves it (irreversibly anonymizing the patient) or
+ * rejects it.
+ *
+ * Endpoints:
+ * - POST /api/v1/patients/:id/erasure-requests - File an erasure request
+ * - GET /api/v1/erasure-requests - List erasure requests
+ * - GET /api/v1/erasure-requests/:id - Get an erasure request
+ * - POST /api/v1/erasure-requests/:id/approve - Approve and anonymize
+ * - POST /api/v1/erasure-requests/:id/reject - Reject
+ */
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    handlers::auth::AppState,
+    models::{
+        ApproveErasureRequest, AuditAction, AuditLog, AuthUser, CreateAuditLog,
+        CreateErasureRequest, EntityType, ListErasureRequestsQuery, PatientErasureRequestResponse,
+        RejectErasureRequest, RequestContext, UserRole,
+    },
+    services::PatientErasureService,
+    utils::{AppError, Result},
+};
+
+/// Only administrators may request or review an erasure
+fn require_admin(auth_user: &AuthUser) -> Result<()> {
+    if auth_user.role != UserRole::Admin {
+        return Err(AppError::Forbidden(
+            "Only administrators can manage patient erasure requests".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn erasure_service(state: &AppState) -> Result<PatientErasureService> {
+    let encryption_key = state
+        .encryption_key
+        .as_ref()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+
+    Ok(PatientErasureService::new(
+        state.pool.clone(),
+        encryption_key.clone(),
+    ))
+}
+
+async fn audit_erasure(
+    state: &AppState,
+    auth_user: &AuthUser,
+    request_ctx: &RequestContext,
+    action: AuditAction,
+    request: &PatientErasureRequestResponse,
+    event: &str,
+) {
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(auth_user.user_id),
+            action,
+            entity_type: EntityType::Patient,
+            entity_id: Some(request.patient_id.to_string()),
+            changes: Some(serde_json::json!({
+                "erasure_request_id": request.id,
+                "type": event,
+                "summary": request.summary,
+            })),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+}
+
+/// File an erasure request for a patient
+///
+/// POST /api/v1/patients/:id/erasure-requests
+///
+/// Returns 409 if the patient already has a pending request or has already
+/// been anonymized.
+pub async fn create_erasure_request(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(patient_id): Path<Uuid>,
+    Json(req): Json<CreateErasureRequest>,
+) -> Result<impl IntoResponse> {
+    require_admin(&auth_user)?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let request = erasure_service(&state)?
+        .create_request(patient_id, req.reason.trim(), auth_user.user_id)
+        .await?;
+
+    audit_erasure(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Create,
+        &request,
+        "erasure_requested",
+    )
+    .await;
+
+    Ok((StatusCode::CREATED, Json(request)))
+}
+
+/// List erasure requests
+///
+/// GET /api/v1/erasure-requests?status=PENDING&patient_id=...
+pub async fn list_erasure_requests(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Query(query): Query<ListErasureRequestsQuery>,
+) -> Result<Json<Vec<PatientErasureRequestResponse>>> {
+    require_admin(&auth_user)?;
+
+    let requests = erasure_service(&state)?.list_requests(&query).await?;
+
+    Ok(Json(requests))
+}
+
+/// Get an erasure request
+///
+/// GET /api/v1/erasure-requests/:id
+pub async fn get_erasure_request(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(request_id): Path<Uuid>,
+) -> Result<Json<PatientErasureRequestResponse>> {
+    require_admin(&auth_user)?;
+
+    let request = erasure_service(&state)?.get_request(request_id).await?;
+
+    Ok(Json(request))
+}
+
+/// Approve an erasure request and anonymize the patient
+///
+/// POST /api/v1/erasure-requests/:id/approve
+///
+/// Irreversible. Returns 403 if the caller filed the request and 409 if it
+/// has already been reviewed.
+pub async fn approve_erasure_request(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(request_id): Path<Uuid>,
+    Json(req): Json<ApproveErasureRequest>,
+) -> Result<Json<PatientErasureRequestResponse>> {
+    require_admin(&auth_user)?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let request = erasure_service(&state)?
+        .approve_request(request_id, &req, auth_user.user_id)
+        .await?;
+
+    audit_erasure(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Delete,
+        &request,
+        "erasure_completed",
+    )
+    .await;
+
+    Ok(Json(request))
+}
+
+/// Reject an erasure request
+///
+/// POST /api/v1/erasure-requests/:id/reject
+pub async fn reject_erasure_request(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(request_id): Path<Uuid>,
+    Json(req): Json<RejectErasureRequest>,
+) -> Result<Json<PatientErasureRequestResponse>> {
+    require_admin(&auth_user)?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let request = erasure_service(&state)?
+        .reject_request(request_id, req.notes.trim(), auth_user.user_id)
+        .await?;
+
+    audit_erasure(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Update,
+        &request,
+        "erasure_rejected",
+    )
+    .await;
+
+    Ok(Json(request))
+}
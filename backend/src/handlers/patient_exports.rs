@@ -0,0 +1,273 @@
+/*!
+ * Patient Data Export Handlers
+ *
+ * GDPR subject access requests: an ADMIN (data controller) requests a
+ * machine-readable bundle of everything stored about a patient, built in the
+ * background as an encrypted zip, then downloads it for hand-over.
+ *
+ * Endpoints:
+ * - POST /api/v1/patients/:id/export - Queue a new export
+ * - GET /api/v1/patients/:id/exports - List the patient's exports
+ * - GET /api/v1/patients/:id/exports/:export_id - Export status
+ * - GET /api/v1/patients/:id/exports/:export_id/download - Download the zip
+ */
+
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Extension, Json,
+};
+use chrono::Utc;
+use std::path::PathBuf;
+use tokio_util::io::ReaderStream;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    handlers::auth::AppState,
+    models::patient_export::DEFAULT_PATIENT_EXPORT_RETENTION_DAYS,
+    models::request_context::with_request_id_scope,
+    models::{
+        AuditAction, AuditLog, AuthUser, CreateAuditLog, CreatePatientExportRequest, EntityType,
+        PatientExportResponse, PatientExportStatus, RequestContext, UserRole,
+    },
+    services::PatientExportService,
+    utils::{AppError, Result},
+};
+
+/// Only administrators may export a patient's data
+fn require_admin(auth_user: &AuthUser) -> Result<()> {
+    if auth_user.role != UserRole::Admin {
+        return Err(AppError::Forbidden(
+            "Only administrators can export patient data".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn export_service(state: &AppState) -> Result<PatientExportService> {
+    let encryption_key = state
+        .encryption_key
+        .as_ref()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+
+    let storage_path = PathBuf::from(
+        std::env::var("DOCUMENT_STORAGE_PATH").unwrap_or_else(|_| "./documents".to_string()),
+    );
+
+    Ok(PatientExportService::new(
+        state.pool.clone(),
+        encryption_key.clone(),
+        storage_path,
+    ))
+}
+
+/// Queue a GDPR subject access export
+///
+/// POST /api/v1/patients/:id/export
+///
+/// Returns 202 with the export in PROCESSING state; clients poll
+/// GET /api/v1/patients/:id/exports/:export_id until it is COMPLETED or
+/// FAILED. The passphrase encrypts the zip and is not stored.
+pub async fn create_patient_export(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(patient_id): Path<Uuid>,
+    Json(req): Json<CreatePatientExportRequest>,
+) -> Result<impl IntoResponse> {
+    require_admin(&auth_user)?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let retention_days = match state
+        .settings_service
+        .get_setting("patient_export_retention_days")
+        .await
+    {
+        Ok(Some(setting)) => setting
+            .setting_value
+            .as_i64()
+            .unwrap_or(DEFAULT_PATIENT_EXPORT_RETENTION_DAYS),
+        _ => DEFAULT_PATIENT_EXPORT_RETENTION_DAYS,
+    };
+
+    let service = export_service(&state)?;
+    let export = service
+        .create_export(patient_id, auth_user.user_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to queue export for patient {}: {:?}", patient_id, e);
+            AppError::Internal(format!("Failed to queue patient export: {:#}", e))
+        })?
+        .ok_or_else(|| AppError::NotFound(format!("Patient {} not found", patient_id)))?;
+
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(auth_user.user_id),
+            action: AuditAction::Export,
+            entity_type: EntityType::Patient,
+            entity_id: Some(patient_id.to_string()),
+            changes: Some(serde_json::json!({
+                "export_id": export.id,
+                "type": "subject_access_export",
+            })),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+
+    let export_id = export.id;
+    let requested_by = auth_user.user_id;
+    tokio::spawn(with_request_id_scope(request_ctx.request_id, async move {
+        if let Err(e) = service
+            .complete_export(
+                export_id,
+                patient_id,
+                requested_by,
+                req.passphrase,
+                retention_days,
+            )
+            .await
+        {
+            tracing::error!("Patient export {} failed: {:#}", export_id, e);
+        }
+    }));
+
+    Ok((StatusCode::ACCEPTED, Json(export)))
+}
+
+/// List a patient's exports
+///
+/// GET /api/v1/patients/:id/exports
+pub async fn list_patient_exports(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(patient_id): Path<Uuid>,
+) -> Result<Json<Vec<PatientExportResponse>>> {
+    require_admin(&auth_user)?;
+
+    let exports = export_service(&state)?
+        .list_exports(patient_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to list exports for patient {}: {:?}", patient_id, e);
+            AppError::Internal("Failed to list patient exports".to_string())
+        })?;
+
+    Ok(Json(exports))
+}
+
+/// Get the status of a patient export
+///
+/// GET /api/v1/patients/:id/exports/:export_id
+pub async fn get_patient_export(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path((patient_id, export_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<PatientExportResponse>> {
+    require_admin(&auth_user)?;
+
+    let export = export_service(&state)?
+        .get_export(patient_id, export_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get patient export {}: {:?}", export_id, e);
+            AppError::Internal("Failed to get patient export".to_string())
+        })?
+        .ok_or_else(|| AppError::NotFound(format!("Export {} not found", export_id)))?;
+
+    Ok(Json(PatientExportResponse::from(export)))
+}
+
+/// Download a completed patient export
+///
+/// GET /api/v1/patients/:id/exports/:export_id/download
+///
+/// Returns 410 Gone once the bundle has expired.
+pub async fn download_patient_export(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path((patient_id, export_id)): Path<(Uuid, Uuid)>,
+) -> Result<axum::response::Response> {
+    require_admin(&auth_user)?;
+
+    let service = export_service(&state)?;
+    let export = service
+        .get_export(patient_id, export_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get patient export {}: {:?}", export_id, e);
+            AppError::Internal("Failed to get patient export".to_string())
+        })?
+        .ok_or_else(|| AppError::NotFound(format!("Export {} not found", export_id)))?;
+
+    if !export.is_downloadable(Utc::now()) {
+        if export.status == PatientExportStatus::Expired.as_str()
+            || export.expires_at.is_some_and(|at| at <= Utc::now())
+        {
+            return Ok((
+                StatusCode::GONE,
+                Json(serde_json::json!({
+                    "error": "EXPORT_EXPIRED",
+                    "message": "This export has expired; request a new one"
+                })),
+            )
+                .into_response());
+        }
+        return Err(AppError::Conflict(format!(
+            "Export {} is not ready ({})",
+            export_id, export.status
+        )));
+    }
+
+    let file_path = export.file_path.clone().unwrap_or_default();
+    let file = tokio::fs::File::open(&file_path).await.map_err(|e| {
+        tracing::error!("Failed to open patient export file {}: {}", file_path, e);
+        AppError::Internal("Failed to open patient export file".to_string())
+    })?;
+
+    if let Err(e) = service.record_download(export_id).await {
+        tracing::warn!(
+            "Failed to record download of patient export {}: {}",
+            export_id,
+            e
+        );
+    }
+
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(auth_user.user_id),
+            action: AuditAction::Export,
+            entity_type: EntityType::Patient,
+            entity_id: Some(patient_id.to_string()),
+            changes: Some(serde_json::json!({
+                "export_id": export_id,
+                "type": "subject_access_export_download",
+            })),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+
+    let body = Body::from_stream(ReaderStream::new(file));
+    let headers = [
+        (header::CONTENT_TYPE, "application/zip".to_string()),
+        (
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", export.download_filename()),
+        ),
+    ];
+
+    Ok((headers, body).into_response())
+}
@@ -0,0 +1,80 @@
+/*!
+ * Patient Merge Handlers
+ *
+ * Merging a duplicate patient record into the surviving one (ADMIN only).
+ *
+ * Endpoints:
+ * - POST /api/v1/patients/:id/merge/:merge_id - Merge patient merge_id into id
+ */
+
+use axum::{
+    extract::{Path, State},
+    Extension, Json,
+};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    handlers::auth::AppState,
+    models::{
+        AuditAction, AuditLog, AuthUser, CreateAuditLog, EntityType, MergePatientsRequest,
+        PatientMergeResponse, RequestContext, UserRole,
+    },
+    services::PatientMergeService,
+    utils::{AppError, Result},
+};
+
+/// Merge a duplicate patient into the surviving record
+///
+/// POST /api/v1/patients/:id/merge/:merge_id
+///
+/// Re-parents the duplicate's appointments, visits, diagnoses,
+/// prescriptions, documents, attachments, insurance and notifications to
+/// patient `id` and tombstones the duplicate. Irreversible; the body must
+/// confirm the duplicate's medical record number.
+///
+/// Returns 409 if either patient is anonymized, already merged or has a
+/// pending erasure request.
+pub async fn merge_patients(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path((keep_id, merge_id)): Path<(Uuid, Uuid)>,
+    Json(req): Json<MergePatientsRequest>,
+) -> Result<Json<PatientMergeResponse>> {
+    if auth_user.role != UserRole::Admin {
+        return Err(AppError::Forbidden(
+            "Only administrators can merge patients".to_string(),
+        ));
+    }
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let merge = PatientMergeService::new(state.pool.clone())
+        .merge_patients(keep_id, merge_id, &req, auth_user.user_id)
+        .await?;
+
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(auth_user.user_id),
+            action: AuditAction::Update,
+            entity_type: EntityType::Patient,
+            entity_id: Some(keep_id.to_string()),
+            changes: Some(serde_json::json!({
+                "type": "patient_merge",
+                "merged_patient_id": merge.merged_patient_id,
+                "merged_medical_record_number": merge.merged_medical_record_number,
+                "reason": req.reason,
+                "summary": merge.summary,
+            })),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+
+    Ok(Json(merge))
+}
@@ -0,0 +1,316 @@
+/*!
+ * Patient Message Handlers
+ *
+ * Secure practice-patient messaging: the provider inbox and the
+ * conversations of a patient. Patients answer through the patient portal.
+ *
+ * Endpoints:
+ * - GET /api/v1/message-threads - Provider inbox
+ * - GET /api/v1/message-threads/unread-count - Unread patient messages
+ * - GET /api/v1/message-threads/:id - Get a conversation with its messages
+ * - PUT /api/v1/message-threads/:id - Close, reopen or assign a conversation
+ * - POST /api/v1/message-threads/:id/messages - Reply
+ * - POST /api/v1/message-threads/:id/read - Mark the patient's messages read
+ * - GET /api/v1/patients/:id/message-threads - List a patient's conversations
+ * - POST /api/v1/patients/:id/message-threads - Start a conversation
+ */
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    handlers::auth::AppState,
+    models::{
+        AuditAction, AuditLog, AuthUser, CreateAuditLog, CreateMessageThreadRequest,
+        CreatePatientMessageRequest, EntityType, MarkMessagesReadResponse, MessageInboxQuery,
+        MessageInboxResponse, MessageThreadDetailResponse, MessageThreadResponse,
+        MessageUnreadCountResponse, PatientMessageResponse, RequestContext,
+        UpdateMessageThreadRequest, UserRole,
+    },
+    services::PatientMessageService,
+    utils::{AppError, Result},
+};
+
+#[cfg(feature = "rbac")]
+use tracing::warn;
+
+/// Check if user has permission to perform action on messages resource
+#[cfg(feature = "rbac")]
+async fn check_permission(state: &AppState, user_role: &UserRole, action: &str) -> Result<()> {
+    let has_permission = state
+        .enforcer
+        .enforce(user_role, "messages", action)
+        .await
+        .map_err(|e| {
+            warn!("RBAC enforcement error: {}", e);
+            AppError::Internal("Failed to check permissions".to_string())
+        })?;
+
+    if !has_permission {
+        return Err(AppError::Forbidden(format!(
+            "User does not have permission to {} messages",
+            action
+        )));
+    }
+
+    Ok(())
+}
+
+/// Fallback for non-RBAC builds - only checks role
+#[cfg(not(feature = "rbac"))]
+async fn check_permission(_state: &AppState, user_role: &UserRole, _action: &str) -> Result<()> {
+    if !matches!(
+        user_role,
+        UserRole::Admin | UserRole::Doctor | UserRole::Nurse
+    ) {
+        return Err(AppError::Forbidden("Insufficient permissions".to_string()));
+    }
+    Ok(())
+}
+
+fn message_service(state: &AppState) -> Result<PatientMessageService> {
+    let encryption_key = state
+        .encryption_key
+        .as_ref()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+
+    Ok(PatientMessageService::new(
+        state.pool.clone(),
+        encryption_key.clone(),
+    ))
+}
+
+/// Audit a conversation change; the payload never contains subjects or
+/// message text
+async fn audit_thread(
+    state: &AppState,
+    auth_user: &AuthUser,
+    request_ctx: &RequestContext,
+    action: AuditAction,
+    thread_id: Uuid,
+    changes: serde_json::Value,
+) {
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(auth_user.user_id),
+            action,
+            entity_type: EntityType::PatientMessageThread,
+            entity_id: Some(thread_id.to_string()),
+            changes: Some(changes),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+}
+
+/// Provider inbox
+///
+/// GET /api/v1/message-threads
+///
+/// **RBAC**: Requires 'read' permission on 'messages' resource
+pub async fn list_message_inbox(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Query(query): Query<MessageInboxQuery>,
+) -> Result<Json<MessageInboxResponse>> {
+    check_permission(&state, &auth_user.role, "read").await?;
+
+    let inbox = message_service(&state)?
+        .inbox(&query, auth_user.user_id)
+        .await?;
+
+    Ok(Json(inbox))
+}
+
+/// Unread patient messages
+///
+/// GET /api/v1/message-threads/unread-count
+///
+/// **RBAC**: Requires 'read' permission on 'messages' resource
+pub async fn get_message_unread_count(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<MessageUnreadCountResponse>> {
+    check_permission(&state, &auth_user.role, "read").await?;
+
+    let count = message_service(&state)?
+        .unread_count(auth_user.user_id)
+        .await?;
+
+    Ok(Json(count))
+}
+
+/// Get a conversation with its messages
+///
+/// GET /api/v1/message-threads/:id
+///
+/// **RBAC**: Requires 'read' permission on 'messages' resource
+pub async fn get_message_thread(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(thread_id): Path<Uuid>,
+) -> Result<Json<MessageThreadDetailResponse>> {
+    check_permission(&state, &auth_user.role, "read").await?;
+
+    let thread = message_service(&state)?
+        .get_thread(thread_id, auth_user.user_id)
+        .await?;
+
+    Ok(Json(thread))
+}
+
+/// Close, reopen or assign a conversation
+///
+/// PUT /api/v1/message-threads/:id
+///
+/// **RBAC**: Requires 'update' permission on 'messages' resource
+pub async fn update_message_thread(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(thread_id): Path<Uuid>,
+    Json(req): Json<UpdateMessageThreadRequest>,
+) -> Result<Json<MessageThreadResponse>> {
+    check_permission(&state, &auth_user.role, "update").await?;
+
+    let thread = message_service(&state)?
+        .update_thread(thread_id, &req, auth_user.user_id)
+        .await?;
+
+    audit_thread(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Update,
+        thread_id,
+        serde_json::json!({
+            "patient_id": thread.patient_id,
+            "status": thread.status,
+            "assigned_to": thread.assigned_to,
+        }),
+    )
+    .await;
+
+    Ok(Json(thread))
+}
+
+/// Reply in a conversation
+///
+/// POST /api/v1/message-threads/:id/messages
+///
+/// **RBAC**: Requires 'create' permission on 'messages' resource
+pub async fn create_patient_message(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(thread_id): Path<Uuid>,
+    Json(req): Json<CreatePatientMessageRequest>,
+) -> Result<(StatusCode, Json<PatientMessageResponse>)> {
+    check_permission(&state, &auth_user.role, "create").await?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let message = message_service(&state)?
+        .reply(thread_id, &req, auth_user.user_id)
+        .await?;
+
+    audit_thread(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Update,
+        thread_id,
+        serde_json::json!({
+            "message_id": message.id,
+            "attachment_ids": req.attachment_ids,
+        }),
+    )
+    .await;
+
+    Ok((StatusCode::CREATED, Json(message)))
+}
+
+/// Mark the patient's messages in a conversation read
+///
+/// POST /api/v1/message-threads/:id/read
+///
+/// **RBAC**: Requires 'read' permission on 'messages' resource
+pub async fn mark_message_thread_read(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(thread_id): Path<Uuid>,
+) -> Result<Json<MarkMessagesReadResponse>> {
+    check_permission(&state, &auth_user.role, "read").await?;
+
+    let marked_read = message_service(&state)?
+        .mark_read(thread_id, auth_user.user_id)
+        .await?;
+
+    Ok(Json(MarkMessagesReadResponse { marked_read }))
+}
+
+/// List a patient's conversations
+///
+/// GET /api/v1/patients/:id/message-threads
+///
+/// **RBAC**: Requires 'read' permission on 'messages' resource
+pub async fn list_patient_message_threads(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(patient_id): Path<Uuid>,
+) -> Result<Json<Vec<MessageThreadResponse>>> {
+    check_permission(&state, &auth_user.role, "read").await?;
+
+    let threads = message_service(&state)?
+        .list_patient_threads(patient_id, auth_user.user_id)
+        .await?;
+
+    Ok(Json(threads))
+}
+
+/// Start a conversation with a patient
+///
+/// POST /api/v1/patients/:id/message-threads
+///
+/// **RBAC**: Requires 'create' permission on 'messages' resource
+pub async fn create_message_thread(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(patient_id): Path<Uuid>,
+    Json(req): Json<CreateMessageThreadRequest>,
+) -> Result<(StatusCode, Json<MessageThreadDetailResponse>)> {
+    check_permission(&state, &auth_user.role, "create").await?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let thread = message_service(&state)?
+        .create_thread(patient_id, &req, auth_user.user_id)
+        .await?;
+
+    audit_thread(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Create,
+        thread.thread.id,
+        serde_json::json!({
+            "patient_id": patient_id,
+            "assigned_to": req.assigned_to,
+            "attachment_ids": req.attachment_ids,
+        }),
+    )
+    .await;
+
+    Ok((StatusCode::CREATED, Json(thread)))
+}
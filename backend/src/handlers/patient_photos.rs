@@ -0,0 +1,248 @@
+/*!
+ * Patient Photo Handlers
+ *
+ * Identification photo on the patient record, encrypted at rest.
+ *
+ * Endpoints:
+ * - PUT /api/v1/patients/:id/photo - Upload or replace the photo (multipart, field `file`)
+ * - GET /api/v1/patients/:id/photo - Get the photo
+ * - DELETE /api/v1/patients/:id/photo - Remove the photo
+ * - GET /api/v1/patients/:id/photo/thumbnail - Get the JPEG thumbnail
+ */
+
+use axum::{
+    extract::{Multipart, Path, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::IntoResponse,
+    Extension, Json,
+};
+use uuid::Uuid;
+
+use crate::{
+    handlers::auth::AppState,
+    models::{
+        uploaded_file::MAX_PATIENT_PHOTO_SIZE, AuditAction, AuditLog, AuthUser, CreateAuditLog,
+        EntityType, PatientPhotoResponse, RequestContext, UserRole,
+    },
+    services::PatientPhotoService,
+    utils::{AppError, Result},
+};
+
+#[cfg(feature = "rbac")]
+use tracing::warn;
+
+/// Check if user has permission to perform action on patients resource
+#[cfg(feature = "rbac")]
+async fn check_permission(state: &AppState, user_role: &UserRole, action: &str) -> Result<()> {
+    let has_permission = state
+        .enforcer
+        .enforce(user_role, "patients", action)
+        .await
+        .map_err(|e| {
+            warn!("RBAC enforcement error: {}", e);
+            AppError::Internal("Failed to check permissions".to_string())
+        })?;
+
+    if !has_permission {
+        return Err(AppError::Forbidden(format!(
+            "User does not have permission to {} patients",
+            action
+        )));
+    }
+
+    Ok(())
+}
+
+/// Fallback for non-RBAC builds - only checks role
+#[cfg(not(feature = "rbac"))]
+async fn check_permission(_state: &AppState, user_role: &UserRole, action: &str) -> Result<()> {
+    // NURSE has read-only access to patients
+    if action != "read" && *user_role == UserRole::Nurse {
+        return Err(AppError::Forbidden("Insufficient permissions".to_string()));
+    }
+    Ok(())
+}
+
+fn photo_service(state: &AppState) -> Result<PatientPhotoService> {
+    let encryption_key = state
+        .encryption_key
+        .as_ref()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+
+    Ok(PatientPhotoService::new(
+        state.pool.clone(),
+        encryption_key.clone(),
+    ))
+}
+
+async fn audit_photo(
+    state: &AppState,
+    auth_user: &AuthUser,
+    request_ctx: &RequestContext,
+    patient_id: Uuid,
+    changes: serde_json::Value,
+) {
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(auth_user.user_id),
+            action: AuditAction::Update,
+            entity_type: EntityType::Patient,
+            entity_id: Some(patient_id.to_string()),
+            changes: Some(changes),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+}
+
+/// Upload or replace a patient's photo
+///
+/// PUT /api/v1/patients/:id/photo
+///
+/// Multipart form with the image in field `file` (JPEG, PNG or WebP, at
+/// most 5 MB). A JPEG thumbnail is generated; both are stored encrypted.
+///
+/// **RBAC**: Requires 'update' permission on 'patients' resource
+pub async fn upload_patient_photo(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(patient_id): Path<Uuid>,
+    mut multipart: Multipart,
+) -> Result<Json<PatientPhotoResponse>> {
+    check_permission(&state, &auth_user.role, "update").await?;
+
+    let mut upload: Option<(Vec<u8>, String)> = None;
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|_| AppError::BadRequest("Failed to parse multipart form".to_string()))?
+    {
+        if field.name() != Some("file") {
+            continue;
+        }
+        let filename = field.file_name().unwrap_or("photo").to_string();
+        let content = field
+            .bytes()
+            .await
+            .map_err(|_| AppError::BadRequest("Failed to read photo".to_string()))?;
+        if content.len() > MAX_PATIENT_PHOTO_SIZE {
+            return Err(AppError::Validation(format!(
+                "Photo exceeds maximum size of {} MB",
+                MAX_PATIENT_PHOTO_SIZE / 1024 / 1024
+            )));
+        }
+        upload = Some((content.to_vec(), filename));
+    }
+
+    let (content, filename) =
+        upload.ok_or_else(|| AppError::BadRequest("No photo provided".to_string()))?;
+
+    let photo = photo_service(&state)?
+        .upload_photo(patient_id, content, &filename, auth_user.user_id)
+        .await?;
+
+    audit_photo(
+        &state,
+        &auth_user,
+        &request_ctx,
+        patient_id,
+        serde_json::json!({
+            "type": "patient_photo_uploaded",
+            "mime_type": photo.mime_type,
+            "file_size_bytes": photo.file_size_bytes,
+        }),
+    )
+    .await;
+
+    Ok(Json(photo))
+}
+
+/// Get a patient's photo
+///
+/// GET /api/v1/patients/:id/photo
+///
+/// **RBAC**: Requires 'read' permission on 'patients' resource
+pub async fn get_patient_photo(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(patient_id): Path<Uuid>,
+) -> Result<impl IntoResponse> {
+    serve_photo(&state, &auth_user, patient_id, false).await
+}
+
+/// Get a patient's photo thumbnail (JPEG)
+///
+/// GET /api/v1/patients/:id/photo/thumbnail
+///
+/// **RBAC**: Requires 'read' permission on 'patients' resource
+pub async fn get_patient_photo_thumbnail(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(patient_id): Path<Uuid>,
+) -> Result<impl IntoResponse> {
+    serve_photo(&state, &auth_user, patient_id, true).await
+}
+
+async fn serve_photo(
+    state: &AppState,
+    auth_user: &AuthUser,
+    patient_id: Uuid,
+    thumbnail: bool,
+) -> Result<impl IntoResponse> {
+    check_permission(state, &auth_user.role, "read").await?;
+
+    let (file, content) = photo_service(state)?
+        .get_photo(patient_id, auth_user.user_id, thumbnail)
+        .await?;
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_str(&file.mime_type)
+            .unwrap_or(HeaderValue::from_static("application/octet-stream")),
+    );
+    // Patient data: never in shared caches
+    headers.insert(
+        header::CACHE_CONTROL,
+        HeaderValue::from_static("private, max-age=300"),
+    );
+    headers.insert(
+        header::X_CONTENT_TYPE_OPTIONS,
+        HeaderValue::from_static("nosniff"),
+    );
+
+    Ok((StatusCode::OK, headers, content))
+}
+
+/// Remove a patient's photo
+///
+/// DELETE /api/v1/patients/:id/photo
+///
+/// **RBAC**: Requires 'update' permission on 'patients' resource
+pub async fn delete_patient_photo(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(patient_id): Path<Uuid>,
+) -> Result<StatusCode> {
+    check_permission(&state, &auth_user.role, "update").await?;
+
+    photo_service(&state)?
+        .delete_photo(patient_id, auth_user.user_id)
+        .await?;
+
+    audit_photo(
+        &state,
+        &auth_user,
+        &request_ctx,
+        patient_id,
+        serde_json::json!({ "type": "patient_photo_removed" }),
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
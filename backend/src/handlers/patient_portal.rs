@@ -0,0 +1,334 @@
+/*!
+ * Patient Portal Handlers
+ *
+ * The patient-facing API, mounted at `/api/portal/v1` apart from the staff
+ * API. Callers are patients holding a portal token; staff RBAC does not
+ * apply, and every query runs with the patient's own portal RLS context.
+ *
+ * Endpoints:
+ * - POST /api/portal/v1/auth/login-link - Email a sign-in link (public)
+ * - POST /api/portal/v1/auth/session - Exchange a sign-in link for a portal token (public)
+ * - POST /api/portal/v1/auth/logout - End the portal session
+ * - GET /api/portal/v1/me - The patient's own record
+ * - PUT /api/portal/v1/me/contact - Update contact details
+ * - GET /api/portal/v1/appointments/upcoming - Upcoming appointments
+ * - GET /api/portal/v1/documents - Signed documents
+ * - GET /api/portal/v1/documents/:id/download - Download a signed document
+ */
+
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use chrono::Utc;
+use std::path::PathBuf;
+use tokio_util::io::ReaderStream;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    handlers::auth::AppState,
+    models::{
+        AuditAction, AuditLog, CreateAuditLog, EntityType, PortalAppointmentResponse,
+        PortalDocumentResponse, PortalLoginLinkRequest, PortalLoginLinkResponse, PortalPatient,
+        PortalProfileResponse, PortalSessionRequest, PortalSessionResponse, RequestContext,
+        UpdatePortalContactRequest,
+    },
+    services::PatientPortalService,
+    utils::{AppError, Result},
+};
+
+/// Portal service for this request (also used by `portal_auth_middleware`)
+pub(crate) fn portal_service(state: &AppState) -> Result<PatientPortalService> {
+    let encryption_key = state
+        .encryption_key
+        .as_ref()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+
+    let storage_path = PathBuf::from(
+        std::env::var("DOCUMENT_STORAGE_PATH").unwrap_or_else(|_| "./documents".to_string()),
+    );
+
+    Ok(PatientPortalService::new(
+        state.pool.clone(),
+        encryption_key.clone(),
+        storage_path,
+    ))
+}
+
+/// The public sign-in endpoints answer 404 while the portal is off
+async fn require_portal_enabled(state: &AppState) -> Result<()> {
+    if !PatientPortalService::is_enabled(&state.settings_service).await {
+        return Err(AppError::NotFound(
+            "Patient portal is not enabled".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Audit a portal action; there is no staff user, the patient is in the
+/// payload
+async fn audit_portal(
+    state: &AppState,
+    request_ctx: &RequestContext,
+    action: AuditAction,
+    entity_type: EntityType,
+    entity_id: Uuid,
+    changes: serde_json::Value,
+) {
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: None,
+            action,
+            entity_type,
+            entity_id: Some(entity_id.to_string()),
+            changes: Some(changes),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+}
+
+/// Email a sign-in link
+///
+/// POST /api/portal/v1/auth/login-link
+///
+/// Always answers 202 with the same message, whether or not the email and
+/// date of birth matched a patient, so the endpoint reveals nothing.
+pub async fn request_portal_login_link(
+    State(state): State<AppState>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Json(req): Json<PortalLoginLinkRequest>,
+) -> Result<(StatusCode, Json<PortalLoginLinkResponse>)> {
+    require_portal_enabled(&state).await?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    if let Err(e) = portal_service(&state)?
+        .request_login_link(
+            state.email_service.as_ref(),
+            &req.email,
+            req.date_of_birth,
+            request_ctx.ip_address.as_deref(),
+        )
+        .await
+    {
+        tracing::error!("Failed to process patient portal sign-in request: {}", e);
+    }
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(PortalLoginLinkResponse {
+            message: "If the details match our records, a sign-in link has been sent to your email address"
+                .to_string(),
+        }),
+    ))
+}
+
+/// Exchange a sign-in link for a portal token
+///
+/// POST /api/portal/v1/auth/session
+pub async fn start_portal_session(
+    State(state): State<AppState>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Json(req): Json<PortalSessionRequest>,
+) -> Result<Json<PortalSessionResponse>> {
+    require_portal_enabled(&state).await?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let (session, patient) = portal_service(&state)?
+        .start_session(
+            &req.token,
+            request_ctx.ip_address.as_deref(),
+            request_ctx.user_agent.as_deref(),
+        )
+        .await?;
+
+    let access_token = state.auth_service.generate_portal_token(
+        &session.patient_id,
+        &session.id,
+        session.expires_at,
+    )?;
+
+    audit_portal(
+        &state,
+        &request_ctx,
+        AuditAction::Login,
+        EntityType::PortalSession,
+        session.id,
+        serde_json::json!({
+            "patient_id": session.patient_id,
+            "expires_at": session.expires_at,
+        }),
+    )
+    .await;
+
+    Ok(Json(PortalSessionResponse {
+        access_token,
+        token_type: "Bearer".to_string(),
+        expires_in: (session.expires_at - Utc::now()).num_seconds().max(0),
+        expires_at: session.expires_at,
+        patient,
+    }))
+}
+
+/// End the portal session
+///
+/// POST /api/portal/v1/auth/logout
+pub async fn portal_logout(
+    State(state): State<AppState>,
+    Extension(portal): Extension<PortalPatient>,
+    Extension(request_ctx): Extension<RequestContext>,
+) -> Result<StatusCode> {
+    portal_service(&state)?
+        .end_session(portal.session_id)
+        .await?;
+
+    audit_portal(
+        &state,
+        &request_ctx,
+        AuditAction::Logout,
+        EntityType::PortalSession,
+        portal.session_id,
+        serde_json::json!({ "patient_id": portal.patient_id }),
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// The patient's own record
+///
+/// GET /api/portal/v1/me
+pub async fn get_portal_profile(
+    State(state): State<AppState>,
+    Extension(portal): Extension<PortalPatient>,
+) -> Result<Json<PortalProfileResponse>> {
+    let profile = portal_service(&state)?.profile(portal.patient_id).await?;
+
+    Ok(Json(profile))
+}
+
+/// Update contact details
+///
+/// PUT /api/portal/v1/me/contact
+pub async fn update_portal_contact(
+    State(state): State<AppState>,
+    Extension(portal): Extension<PortalPatient>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Json(req): Json<UpdatePortalContactRequest>,
+) -> Result<Json<PortalProfileResponse>> {
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    if req.is_empty() {
+        return Err(AppError::BadRequest(
+            "No contact details to update".to_string(),
+        ));
+    }
+
+    let (profile, propagation) = portal_service(&state)?
+        .update_contact(portal.patient_id, &req)
+        .await?;
+
+    audit_portal(
+        &state,
+        &request_ctx,
+        AuditAction::Update,
+        EntityType::Patient,
+        portal.patient_id,
+        serde_json::json!({
+            "source": "patient_portal",
+            "portal_session_id": portal.session_id,
+            "fields": req.changed_fields(),
+            "contact_propagation": propagation,
+        }),
+    )
+    .await;
+
+    Ok(Json(profile))
+}
+
+/// Upcoming appointments
+///
+/// GET /api/portal/v1/appointments/upcoming
+pub async fn list_portal_upcoming_appointments(
+    State(state): State<AppState>,
+    Extension(portal): Extension<PortalPatient>,
+) -> Result<Json<Vec<PortalAppointmentResponse>>> {
+    let appointments = portal_service(&state)?
+        .upcoming_appointments(portal.patient_id)
+        .await?;
+
+    Ok(Json(appointments))
+}
+
+/// Signed documents
+///
+/// GET /api/portal/v1/documents
+pub async fn list_portal_documents(
+    State(state): State<AppState>,
+    Extension(portal): Extension<PortalPatient>,
+) -> Result<Json<Vec<PortalDocumentResponse>>> {
+    let documents = portal_service(&state)?.documents(portal.patient_id).await?;
+
+    Ok(Json(documents))
+}
+
+/// Download a signed document
+///
+/// GET /api/portal/v1/documents/:id/download
+pub async fn download_portal_document(
+    State(state): State<AppState>,
+    Extension(portal): Extension<PortalPatient>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(document_id): Path<Uuid>,
+) -> Result<Response> {
+    let (file_path, filename) = portal_service(&state)?
+        .document_file(portal.patient_id, document_id)
+        .await?;
+
+    let file = tokio::fs::File::open(&file_path).await.map_err(|e| {
+        tracing::error!(
+            "Failed to open document file {}: {}",
+            file_path.display(),
+            e
+        );
+        AppError::Internal("Failed to open document file".to_string())
+    })?;
+
+    audit_portal(
+        &state,
+        &request_ctx,
+        AuditAction::Read,
+        EntityType::Document,
+        document_id,
+        serde_json::json!({
+            "source": "patient_portal",
+            "patient_id": portal.patient_id,
+            "portal_session_id": portal.session_id,
+            "type": "document_download",
+        }),
+    )
+    .await;
+
+    let body = Body::from_stream(ReaderStream::new(file));
+    let headers = [
+        (header::CONTENT_TYPE, "application/pdf".to_string()),
+        (
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", filename),
+        ),
+    ];
+
+    Ok((headers, body).into_response())
+}
@@ -0,0 +1,371 @@
+/*!
+ * Patient Problem Handlers
+ *
+ * Longitudinal problem list on the patient record, and promotion of a visit
+ * diagnosis onto it.
+ *
+ * Endpoints:
+ * - GET /api/v1/patients/:id/problems - List a patient's problems
+ * - POST /api/v1/patients/:id/problems - Add a problem
+ * - GET /api/v1/patients/:id/problems/:problem_id - Get a problem
+ * - PUT /api/v1/patients/:id/problems/:problem_id - Update a problem
+ * - DELETE /api/v1/patients/:id/problems/:problem_id - Delete a problem
+ * - POST /api/v1/patients/:id/problems/:problem_id/visits - Link a visit
+ * - DELETE /api/v1/patients/:id/problems/:problem_id/visits/:visit_id - Unlink a visit
+ * - POST /api/v1/diagnoses/:id/promote - Promote a visit diagnosis to a problem
+ */
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    handlers::auth::AppState,
+    models::{
+        AuditAction, AuditLog, AuthUser, CreateAuditLog, CreatePatientProblemRequest, EntityType,
+        LinkProblemVisitRequest, ListPatientProblemsQuery, PatientProblemResponse, RequestContext,
+        UpdatePatientProblemRequest, UserRole,
+    },
+    services::PatientProblemService,
+    utils::{AppError, Result},
+};
+
+#[cfg(feature = "rbac")]
+use tracing::warn;
+
+/// Check if user has permission to perform action on problems resource
+#[cfg(feature = "rbac")]
+async fn check_permission(state: &AppState, user_role: &UserRole, action: &str) -> Result<()> {
+    let has_permission = state
+        .enforcer
+        .enforce(user_role, "problems", action)
+        .await
+        .map_err(|e| {
+            warn!("RBAC enforcement error: {}", e);
+            AppError::Internal("Failed to check permissions".to_string())
+        })?;
+
+    if !has_permission {
+        return Err(AppError::Forbidden(format!(
+            "User does not have permission to {} problems",
+            action
+        )));
+    }
+
+    Ok(())
+}
+
+/// Fallback for non-RBAC builds - only checks role
+#[cfg(not(feature = "rbac"))]
+async fn check_permission(_state: &AppState, user_role: &UserRole, action: &str) -> Result<()> {
+    let allowed = match action {
+        "read" => matches!(
+            user_role,
+            UserRole::Admin | UserRole::Doctor | UserRole::Nurse
+        ),
+        _ => matches!(user_role, UserRole::Admin | UserRole::Doctor),
+    };
+
+    if !allowed {
+        return Err(AppError::Forbidden("Insufficient permissions".to_string()));
+    }
+    Ok(())
+}
+
+fn problem_service(state: &AppState) -> Result<PatientProblemService> {
+    let encryption_key = state
+        .encryption_key
+        .as_ref()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+
+    Ok(PatientProblemService::new(
+        state.pool.clone(),
+        encryption_key.clone(),
+    ))
+}
+
+/// Audit a problem change; the payload never contains the clinical notes
+async fn audit_problem(
+    state: &AppState,
+    auth_user: &AuthUser,
+    request_ctx: &RequestContext,
+    action: AuditAction,
+    problem_id: Uuid,
+    changes: serde_json::Value,
+) {
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(auth_user.user_id),
+            action,
+            entity_type: EntityType::PatientProblem,
+            entity_id: Some(problem_id.to_string()),
+            changes: Some(changes),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+}
+
+/// List a patient's problems
+///
+/// GET /api/v1/patients/:id/problems
+///
+/// **RBAC**: Requires 'read' permission on 'problems' resource
+pub async fn list_patient_problems(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(patient_id): Path<Uuid>,
+    Query(query): Query<ListPatientProblemsQuery>,
+) -> Result<Json<Vec<PatientProblemResponse>>> {
+    check_permission(&state, &auth_user.role, "read").await?;
+
+    let problems = problem_service(&state)?
+        .list_problems(patient_id, &query, auth_user.user_id)
+        .await?;
+
+    Ok(Json(problems))
+}
+
+/// Add a problem
+///
+/// POST /api/v1/patients/:id/problems
+///
+/// **RBAC**: Requires 'create' permission on 'problems' resource
+pub async fn create_patient_problem(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(patient_id): Path<Uuid>,
+    Json(req): Json<CreatePatientProblemRequest>,
+) -> Result<(StatusCode, Json<PatientProblemResponse>)> {
+    check_permission(&state, &auth_user.role, "create").await?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let problem = problem_service(&state)?
+        .create_problem(patient_id, &req, auth_user.user_id)
+        .await?;
+
+    audit_problem(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Create,
+        problem.id,
+        serde_json::json!({
+            "patient_id": patient_id,
+            "icd10_code": problem.icd10_code,
+            "onset_date": problem.onset_date,
+            "visit_id": req.visit_id,
+        }),
+    )
+    .await;
+
+    Ok((StatusCode::CREATED, Json(problem)))
+}
+
+/// Get a problem
+///
+/// GET /api/v1/patients/:id/problems/:problem_id
+///
+/// **RBAC**: Requires 'read' permission on 'problems' resource
+pub async fn get_patient_problem(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path((patient_id, problem_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<PatientProblemResponse>> {
+    check_permission(&state, &auth_user.role, "read").await?;
+
+    let problem = problem_service(&state)?
+        .get_problem(patient_id, problem_id, auth_user.user_id)
+        .await?;
+
+    Ok(Json(problem))
+}
+
+/// Update a problem
+///
+/// PUT /api/v1/patients/:id/problems/:problem_id
+///
+/// **RBAC**: Requires 'update' permission on 'problems' resource
+pub async fn update_patient_problem(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path((patient_id, problem_id)): Path<(Uuid, Uuid)>,
+    Json(req): Json<UpdatePatientProblemRequest>,
+) -> Result<Json<PatientProblemResponse>> {
+    check_permission(&state, &auth_user.role, "update").await?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let problem = problem_service(&state)?
+        .update_problem(patient_id, problem_id, &req, auth_user.user_id)
+        .await?;
+
+    audit_problem(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Update,
+        problem_id,
+        serde_json::json!({
+            "patient_id": patient_id,
+            "status": req.status,
+            "onset_date": req.onset_date,
+            "resolved_date": problem.resolved_date,
+        }),
+    )
+    .await;
+
+    Ok(Json(problem))
+}
+
+/// Delete a problem
+///
+/// DELETE /api/v1/patients/:id/problems/:problem_id
+///
+/// The problem is marked deleted and kept with the medical record.
+///
+/// **RBAC**: Requires 'delete' permission on 'problems' resource
+pub async fn delete_patient_problem(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path((patient_id, problem_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode> {
+    check_permission(&state, &auth_user.role, "delete").await?;
+
+    problem_service(&state)?
+        .delete_problem(patient_id, problem_id, auth_user.user_id)
+        .await?;
+
+    audit_problem(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Delete,
+        problem_id,
+        serde_json::json!({ "patient_id": patient_id }),
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Link a visit to a problem
+///
+/// POST /api/v1/patients/:id/problems/:problem_id/visits
+///
+/// **RBAC**: Requires 'update' permission on 'problems' resource
+pub async fn link_patient_problem_visit(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path((patient_id, problem_id)): Path<(Uuid, Uuid)>,
+    Json(req): Json<LinkProblemVisitRequest>,
+) -> Result<Json<PatientProblemResponse>> {
+    check_permission(&state, &auth_user.role, "update").await?;
+
+    let problem = problem_service(&state)?
+        .link_visit(patient_id, problem_id, req.visit_id, auth_user.user_id)
+        .await?;
+
+    audit_problem(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Update,
+        problem_id,
+        serde_json::json!({ "patient_id": patient_id, "linked_visit_id": req.visit_id }),
+    )
+    .await;
+
+    Ok(Json(problem))
+}
+
+/// Unlink a visit from a problem
+///
+/// DELETE /api/v1/patients/:id/problems/:problem_id/visits/:visit_id
+///
+/// **RBAC**: Requires 'update' permission on 'problems' resource
+pub async fn unlink_patient_problem_visit(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path((patient_id, problem_id, visit_id)): Path<(Uuid, Uuid, Uuid)>,
+) -> Result<StatusCode> {
+    check_permission(&state, &auth_user.role, "update").await?;
+
+    problem_service(&state)?
+        .unlink_visit(patient_id, problem_id, visit_id, auth_user.user_id)
+        .await?;
+
+    audit_problem(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Update,
+        problem_id,
+        serde_json::json!({ "patient_id": patient_id, "unlinked_visit_id": visit_id }),
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Promote a visit diagnosis to the patient's problem list
+///
+/// POST /api/v1/diagnoses/:id/promote
+///
+/// Returns 201 with the new problem, or 200 with the existing active problem
+/// for the same ICD-10 code, now linked to the diagnosis's visit.
+///
+/// **RBAC**: Requires 'create' permission on 'problems' resource
+pub async fn promote_diagnosis_to_problem(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(diagnosis_id): Path<Uuid>,
+) -> Result<(StatusCode, Json<PatientProblemResponse>)> {
+    check_permission(&state, &auth_user.role, "create").await?;
+
+    let promoted = problem_service(&state)?
+        .promote_diagnosis(diagnosis_id, auth_user.user_id)
+        .await?;
+    let problem = promoted.problem;
+
+    audit_problem(
+        &state,
+        &auth_user,
+        &request_ctx,
+        if promoted.created {
+            AuditAction::Create
+        } else {
+            AuditAction::Update
+        },
+        problem.id,
+        serde_json::json!({
+            "patient_id": problem.patient_id,
+            "icd10_code": problem.icd10_code,
+            "source_diagnosis_id": diagnosis_id,
+        }),
+    )
+    .await;
+
+    let status = if promoted.created {
+        StatusCode::CREATED
+    } else {
+        StatusCode::OK
+    };
+    Ok((status, Json(problem)))
+}
@@ -6,22 +6,33 @@
 
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{header, HeaderMap, StatusCode},
     response::IntoResponse,
     Extension, Json,
 };
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 use uuid::Uuid;
 use validator::Validate;
 
 use crate::{
+    db::rls::{apply_rls_context, target_trash_record},
     handlers::auth::AppState,
     models::{
+        patient::is_fiscal_code_conflict,
         AuditAction, AuditLog, CreateAuditLog, CreatePatientRequest, EntityType,
-        Patient, PatientDto, PatientSearchFilter, RequestContext, UpdatePatientRequest, UserRole,
+        Patient, PatientAutocompleteQuery, PatientChartQuery, PatientChartSections, PatientDto,
+        PatientSearchFilter,
+        RequestContext, UpdatePatientRequest, UserRole,
+    },
+    services::{
+        contact_propagation::{propagate_contact_change, ContactDetails},
+        PatientChartService, PatientService,
+    },
+    utils::{
+        concurrency::{expected_version, is_stale, lock_row, version_conflict, version_tag},
+        AppError, Result,
     },
-    services::PatientService,
-    utils::{AppError, Result},
 };
 
 /// Helper function to set RLS context in a transaction
@@ -31,32 +42,12 @@ async fn set_rls_in_transaction(
     user_id: &Uuid,
     user_role: &UserRole,
 ) -> Result<()> {
-    let role_str = match user_role {
-        UserRole::Admin => "ADMIN",
-        UserRole::Doctor => "DOCTOR",
-    };
-
-    // Use set_config() which supports parameterized queries
-    // Third parameter 'true' makes it local to the current transaction
-    sqlx::query("SELECT set_config('app.current_user_id', $1, true)")
-        .bind(user_id.to_string())
-        .execute(&mut **tx)
-        .await
-        .map_err(|e| {
-            tracing::error!("Failed to set RLS user context: {}", e);
-            AppError::Internal("Failed to set security context".to_string())
-        })?;
-
-    sqlx::query("SELECT set_config('app.current_user_role', $1, true)")
-        .bind(role_str)
-        .execute(&mut **tx)
+    apply_rls_context(&mut **tx, *user_id, &user_role.to_string())
         .await
         .map_err(|e| {
-            tracing::error!("Failed to set RLS role context: {}", e);
+            tracing::error!("Failed to set RLS context: {}", e);
             AppError::Internal("Failed to set security context".to_string())
-        })?;
-
-    Ok(())
+        })
 }
 
 #[cfg(feature = "rbac")]
@@ -105,9 +96,16 @@ async fn check_permission(
                 ));
             }
         }
-        "create" | "read" | "update" => {
-            // Both ADMIN and DOCTOR can perform these actions
-            // No additional check needed
+        "create" | "update" => {
+            // NURSE has read-only access to patients
+            if *user_role == UserRole::Nurse {
+                return Err(AppError::Forbidden(
+                    "Insufficient permissions".to_string(),
+                ));
+            }
+        }
+        "read" => {
+            // All roles can read patients
         }
         _ => {
             return Err(AppError::Forbidden(format!(
@@ -120,6 +118,43 @@ async fn check_permission(
     Ok(())
 }
 
+/// Reject clinical fields from roles limited to demographics (RECEPTIONIST)
+fn reject_clinical_fields(user_role: &UserRole, fields: Vec<&'static str>) -> Result<()> {
+    if *user_role == UserRole::Receptionist && !fields.is_empty() {
+        return Err(AppError::Forbidden(format!(
+            "Receptionists cannot set clinical fields: {}",
+            fields.join(", ")
+        )));
+    }
+    Ok(())
+}
+
+/// Whether the role may read a resource, for the sections of the chart
+#[cfg(feature = "rbac")]
+async fn can_read(state: &AppState, user_role: &UserRole, resource: &str) -> bool {
+    state
+        .enforcer
+        .enforce(user_role, resource, "read")
+        .await
+        .unwrap_or_else(|e| {
+            warn!("RBAC enforcement error: {}", e);
+            false
+        })
+}
+
+/// Fallback for non-RBAC builds - clinical data for clinical roles only
+#[cfg(not(feature = "rbac"))]
+async fn can_read(_state: &AppState, user_role: &UserRole, resource: &str) -> bool {
+    match resource {
+        "appointments" => true,
+        "generated_documents" => matches!(user_role, UserRole::Admin | UserRole::Doctor),
+        _ => matches!(
+            user_role,
+            UserRole::Admin | UserRole::Doctor | UserRole::Nurse
+        ),
+    }
+}
+
 /// Create patient handler
 ///
 /// POST /api/v1/patients
@@ -154,6 +189,7 @@ pub async fn create_patient(
 
     // Check RBAC permission
     check_permission(&state, &user_role, "create").await?;
+    reject_clinical_fields(&user_role, data.clinical_fields())?;
 
     // Validate request
     data.validate()
@@ -207,6 +243,9 @@ pub async fn create_patient(
     let patient_result = Patient::create(&mut *tx, data.clone(), user_id, encryption_key)
         .await
         .map_err(|e| {
+            if is_fiscal_code_conflict(&e) {
+                return AppError::Conflict("Patient with same fiscal code already exists".to_string());
+            }
             tracing::error!("Failed to create patient: {}", e);
             AppError::Internal(format!("Failed to create patient: {}", e))
         })?;
@@ -305,14 +344,88 @@ pub async fn get_patient(
         AppError::Internal("Failed to retrieve patient data".to_string())
     })?;
 
-    Ok(Json(patient))
+    Ok(([(header::ETAG, version_tag(patient.updated_at))], Json(patient)))
+}
+
+/// Get patient chart handler
+///
+/// GET /api/v1/patients/:id/chart?visits_limit=5&appointments_limit=5&documents_limit=5
+///
+/// Everything shown when a chart is opened in one response: recent visits,
+/// active prescriptions, problem list, upcoming appointments, latest vitals
+/// and recent documents. Sections the role may not read (e.g. clinical data
+/// for RECEPTIONIST) are null.
+///
+/// # Authorization
+/// Requires 'read' permission on 'patients', plus 'read' on each section's
+/// resource
+pub async fn get_patient_chart(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(user_role): Extension<UserRole>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(patient_id): Path<Uuid>,
+    Query(query): Query<PatientChartQuery>,
+) -> Result<impl IntoResponse> {
+    check_permission(&state, &user_role, "read").await?;
+
+    query
+        .validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let encryption_key = state
+        .encryption_key
+        .clone()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+
+    let sections = PatientChartSections {
+        visits: can_read(&state, &user_role, "visits").await,
+        prescriptions: can_read(&state, &user_role, "prescriptions").await,
+        problems: can_read(&state, &user_role, "problems").await,
+        appointments: can_read(&state, &user_role, "appointments").await,
+        documents: can_read(&state, &user_role, "generated_documents").await,
+    };
+
+    let storage_path = PathBuf::from(
+        std::env::var("DOCUMENT_STORAGE_PATH").unwrap_or_else(|_| "./documents".to_string()),
+    );
+
+    let chart = PatientChartService::new(state.pool.clone(), encryption_key, storage_path)
+        .get_chart(patient_id, &query, sections, user_id, &user_role)
+        .await?;
+
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(user_id),
+            action: AuditAction::Read,
+            entity_type: EntityType::Patient,
+            entity_id: Some(patient_id.to_string()),
+            changes: Some(serde_json::json!({
+                "type": "patient_chart",
+                "visits": chart.recent_visits.as_ref().map(Vec::len),
+                "prescriptions": chart.active_prescriptions.as_ref().map(Vec::len),
+                "problems": chart.problems.as_ref().map(Vec::len),
+                "appointments": chart.upcoming_appointments.as_ref().map(Vec::len),
+                "documents": chart.recent_documents.as_ref().map(Vec::len),
+            })),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+
+    Ok(Json(chart))
 }
 
 /// Update patient handler
 ///
 /// PUT /api/v1/patients/:id
 ///
-/// Updates patient record with encrypted fields.
+/// Updates patient record with encrypted fields. With `If-Match` or
+/// `expected_version`, an update of a patient changed since is refused with
+/// 409 and the current patient.
 ///
 /// # Authorization
 /// Requires ADMIN or DOCTOR role
@@ -322,7 +435,8 @@ pub async fn update_patient(
     Extension(user_role): Extension<UserRole>,
     Extension(request_ctx): Extension<RequestContext>,
     Path(patient_id): Path<Uuid>,
-    Json(data): Json<UpdatePatientRequest>,
+    headers: HeaderMap,
+    Json(mut data): Json<UpdatePatientRequest>,
 ) -> Result<impl IntoResponse> {
     tracing::info!(
         "Updating patient {} by user: {} (role: {:?})",
@@ -333,10 +447,12 @@ pub async fn update_patient(
 
     // Check RBAC permission
     check_permission(&state, &user_role, "update").await?;
+    reject_clinical_fields(&user_role, data.clinical_fields())?;
 
     // Validate request
     data.validate()
         .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+    data.expected_version = expected_version(&headers, data.expected_version)?;
 
     let encryption_key = state
         .encryption_key
@@ -351,6 +467,11 @@ pub async fn update_patient(
 
     set_rls_in_transaction(&mut tx, &user_id, &user_role).await?;
 
+    // Hold the row until commit so the version check cannot go stale
+    if data.expected_version.is_some() {
+        lock_row(&mut *tx, "patients", patient_id).await?;
+    }
+
     // Fetch existing patient
     let existing = Patient::find_by_id(&mut *tx, patient_id)
         .await
@@ -360,14 +481,43 @@ pub async fn update_patient(
         })?
         .ok_or_else(|| AppError::NotFound(format!("Patient {} not found", patient_id)))?;
 
+    let existing_dto = existing.decrypt(encryption_key).map_err(|e| {
+        tracing::error!("Failed to decrypt patient: {}", e);
+        AppError::Internal("Failed to retrieve patient data".to_string())
+    })?;
+
+    if is_stale(data.expected_version, existing.updated_at) {
+        return Err(version_conflict(&existing_dto));
+    }
+
+    let old_contact = ContactDetails::from_patient(&existing_dto);
+
     // Update patient within transaction (passing existing patient)
     let patient_result = Patient::update_with_existing(&mut *tx, patient_id, existing, data.clone(), user_id, encryption_key)
         .await
         .map_err(|e| {
+            if is_fiscal_code_conflict(&e) {
+                return AppError::Conflict("Patient with same fiscal code already exists".to_string());
+            }
             tracing::error!("Failed to update patient: {}", e);
             AppError::Internal(format!("Failed to update patient: {}", e))
         })?;
 
+    // Keep queued notifications in step with the new contact details
+    let new_contact = patient_result
+        .decrypt(encryption_key)
+        .map(|p| ContactDetails::from_patient(&p))
+        .map_err(|e| {
+            tracing::error!("Failed to decrypt patient: {}", e);
+            AppError::Internal("Failed to retrieve patient data".to_string())
+        })?;
+    let propagation = propagate_contact_change(&mut tx, patient_id, &old_contact, &new_contact)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to propagate contact change for patient {}: {}", patient_id, e);
+            AppError::Internal("Failed to update patient".to_string())
+        })?;
+
     tx.commit().await.map_err(|e| {
         tracing::error!("Failed to commit transaction: {}", e);
         AppError::Internal("Database transaction failed".to_string())
@@ -395,16 +545,43 @@ pub async fn update_patient(
     )
     .await;
 
+    if propagation.has_changes() {
+        tracing::info!(
+            "Patient {} contact change propagated: {} notifications updated, {} cancelled",
+            patient_id,
+            propagation.notifications_updated,
+            propagation.notifications_cancelled
+        );
+
+        let _ = AuditLog::create(
+            &state.pool,
+            CreateAuditLog {
+                user_id: Some(user_id),
+                action: AuditAction::Update,
+                entity_type: EntityType::Notification,
+                entity_id: Some(patient_id.to_string()),
+                changes: Some(serde_json::json!({
+                    "contact_propagation": propagation,
+                })),
+                ip_address: request_ctx.ip_address.clone(),
+                user_agent: request_ctx.user_agent.clone(),
+                request_id: Some(request_ctx.request_id),
+            },
+        )
+        .await;
+    }
+
     tracing::info!("Patient {} updated successfully", patient_id);
 
-    Ok(Json(patient))
+    Ok(([(header::ETAG, version_tag(patient.updated_at))], Json(patient)))
 }
 
-/// Delete (deactivate) patient handler
+/// Delete patient handler
 ///
 /// DELETE /api/v1/patients/:id
 ///
-/// Soft deletes a patient by setting status to INACTIVE.
+/// Moves the patient to the recycle bin (GET /api/v1/trash), from which it
+/// can be restored until the RECYCLE_BIN retention rule purges it.
 ///
 /// # Authorization
 /// Requires ADMIN role only (more destructive operation)
@@ -447,7 +624,8 @@ pub async fn delete_patient(
     }
 
     // Soft delete patient within transaction
-    Patient::soft_delete(&mut *tx, patient_id)
+    target_trash_record(&mut tx, patient_id).await?;
+    Patient::soft_delete(&mut *tx, patient_id, user_id)
         .await
         .map_err(|e| {
             tracing::error!("Failed to delete patient: {}", e);
@@ -484,8 +662,8 @@ pub async fn delete_patient(
 ///
 /// POST /api/v1/patients/:id/reactivate
 ///
-/// Reactivates a previously soft-deleted (INACTIVE) patient by setting
-/// their status back to ACTIVE.
+/// Reactivates an INACTIVE patient by setting their status back to ACTIVE.
+/// Deleted patients are restored from the recycle bin instead.
 ///
 /// # Authorization
 /// Requires ADMIN role
@@ -741,6 +919,58 @@ pub async fn search_patients(
     Ok(Json(response))
 }
 
+/// Autocomplete patients handler
+///
+/// GET /api/v1/patients/autocomplete?q=ross&status=ACTIVE
+///
+/// Top matches of part of a name, fiscal code or phone number, through the
+/// trigram blind index instead of decrypting every patient.
+///
+/// # Authorization
+/// Same as search
+pub async fn autocomplete_patients(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(user_role): Extension<UserRole>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Query(query): Query<PatientAutocompleteQuery>,
+) -> Result<impl IntoResponse> {
+    check_permission(&state, &user_role, "read").await?;
+
+    let encryption_key = state
+        .encryption_key
+        .as_ref()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+
+    let mut tx = state.pool.begin().await.map_err(|e| {
+        tracing::error!("Failed to begin transaction: {}", e);
+        AppError::Internal("Database transaction failed".to_string())
+    })?;
+
+    set_rls_in_transaction(&mut tx, &user_id, &user_role).await?;
+
+    let patient_service = PatientService::new(state.pool.clone(), encryption_key.clone());
+    let patients = patient_service
+        .autocomplete_patients(&mut *tx, query, Some(user_id), Some(&request_ctx))
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to autocomplete patients: {}", e);
+            AppError::Internal(format!("Failed to autocomplete patients: {}", e))
+        })?;
+
+    tx.commit().await.map_err(|e| {
+        tracing::error!("Failed to commit transaction: {}", e);
+        AppError::Internal("Database transaction failed".to_string())
+    })?;
+
+    let response = serde_json::json!({
+        "patients": patients,
+        "total": patients.len()
+    });
+
+    Ok(Json(response))
+}
+
 /// Get patient statistics handler
 ///
 /// GET /api/v1/patients/statistics
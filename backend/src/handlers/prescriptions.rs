@@ -17,13 +17,37 @@ use validator::Validate;
 use crate::{
     handlers::auth::AppState,
     models::{
-        AuditAction, AuditLog, AuthUser, CreateAuditLog, CreatePrescriptionRequest,
-        EntityType, RequestContext, UpdatePrescriptionRequest, UserRole,
+        AuditAction, AuditLog, AuthUser, CreateAuditLog, CreateMedicationFavoriteRequest,
+        CreatePrescriptionRequest, EntityType, MedicationFavorite, OverdueRefill,
+        OverdueRefillsQuery, QuickPickQuery, QuickPickResponse, RefillRemindersResponse,
+        RequestContext, SendRefillRemindersRequest, UpdateMedicationFavoriteRequest,
+        UpdatePrescriptionRequest, UserRole,
+    },
+    services::{
+        CacheNamespace, MedicationFavoriteService, NotificationService, PrescriptionRefillService,
+        PrescriptionService,
     },
-    services::PrescriptionService,
     utils::{AppError, Result},
 };
 
+#[cfg(feature = "pdf-export")]
+use crate::{
+    models::prescription_print_batch::{INLINE_PRINT_BATCH_LIMIT, MAX_PRINT_BATCH_PRESCRIPTIONS},
+    models::request_context::with_request_id_scope,
+    models::{
+        CreatePrescriptionPrintBatchRequest, GenerateSsnPrescriptionRequest,
+        PrescriptionPrintBatchResponse, PrescriptionPrintLayout, PrescriptionResponse,
+        PrescriptionStatus, SSN_MAX_PACKAGES,
+    },
+    services::DocumentService,
+};
+#[cfg(feature = "pdf-export")]
+use axum::{body::Body, http::header};
+#[cfg(feature = "pdf-export")]
+use std::path::PathBuf;
+#[cfg(feature = "pdf-export")]
+use tokio_util::io::ReaderStream;
+
 #[cfg(feature = "rbac")]
 use tracing::warn;
 
@@ -142,6 +166,16 @@ pub struct PatientPrescriptionsQuery {
     pub active_only: Option<bool>,
 }
 
+/// Request body for checking a drug against a patient's allergies
+#[derive(Debug, Deserialize, Validate)]
+pub struct CheckPrescriptionAllergiesRequest {
+    pub patient_id: Uuid,
+    #[validate(length(min = 1, max = 255, message = "Medication name must be 1-255 characters"))]
+    pub medication_name: String,
+    #[validate(length(max = 255, message = "Generic name too long (max 255 chars)"))]
+    pub generic_name: Option<String>,
+}
+
 /// Request body for discontinuing a prescription
 #[derive(Debug, Deserialize, Validate)]
 pub struct DiscontinuePrescriptionRequest {
@@ -270,13 +304,37 @@ pub async fn create_prescription(
     let prescription = prescription_service
         .create_prescription(req.clone(), auth_user.user_id, &role_str)
         .await
-        .map_err(|e| {
-            tracing::error!("Failed to create prescription: {:?}", e);
-            tracing::error!("Request data: patient_id={}, medication={}, provider_id={}",
-                req.patient_id, req.medication_name, req.provider_id);
-            AppError::Internal(format!("Failed to create prescription: {:?}", e))
+        .map_err(|e| match e.downcast::<AppError>() {
+            // Allergy conflicts without an override reason
+            Ok(app_error) => app_error,
+            Err(e) => {
+                tracing::error!("Failed to create prescription: {:?}", e);
+                tracing::error!("Request data: patient_id={}, medication={}, provider_id={}",
+                    req.patient_id, req.medication_name, req.provider_id);
+                AppError::Internal(format!("Failed to create prescription: {:?}", e))
+            }
         })?;
 
+    let mut changes = serde_json::json!({
+        "patient_id": req.patient_id,
+        "medication_name": req.medication_name,
+        "dosage": req.dosage,
+    });
+    if !prescription.allergy_alerts.is_empty() {
+        changes["allergy_override"] = serde_json::json!({
+            "reason": req.allergy_override_reason,
+            "alerts": prescription
+                .allergy_alerts
+                .iter()
+                .map(|a| serde_json::json!({
+                    "allergy_id": a.allergy_id,
+                    "match_type": a.match_type,
+                    "drug_class": a.drug_class,
+                }))
+                .collect::<Vec<_>>(),
+        });
+    }
+
     // Create audit log for prescription creation
     let _ = AuditLog::create(
         &state.pool,
@@ -285,11 +343,7 @@ pub async fn create_prescription(
             action: AuditAction::Create,
             entity_type: EntityType::Prescription,
             entity_id: Some(prescription.id.to_string()),
-            changes: Some(serde_json::json!({
-                "patient_id": req.patient_id,
-                "medication_name": req.medication_name,
-                "dosage": req.dosage,
-            })),
+            changes: Some(changes),
             ip_address: request_ctx.ip_address.clone(),
             user_agent: request_ctx.user_agent.clone(),
             request_id: Some(request_ctx.request_id),
@@ -300,6 +354,50 @@ pub async fn create_prescription(
     Ok((StatusCode::CREATED, Json(prescription)))
 }
 
+/// Check a drug against the patient's active allergies before prescribing it
+///
+/// POST /api/v1/prescriptions/allergy-check
+///
+/// Returns the alerts `create_prescription` would require an
+/// `allergy_override_reason` for; an empty list means no conflict.
+///
+/// **RBAC**: Requires 'create' permission on 'prescriptions' resource
+/// **Roles**: ADMIN, DOCTOR
+pub async fn check_prescription_allergies(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Json(req): Json<CheckPrescriptionAllergiesRequest>,
+) -> Result<impl IntoResponse> {
+    check_permission(&state, &auth_user.role, "create").await?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let encryption_key = state
+        .encryption_key
+        .as_ref()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+
+    let role_str = format!("{:?}", auth_user.role).to_uppercase();
+
+    let prescription_service = PrescriptionService::new(state.pool.clone(), encryption_key.clone());
+    let alerts = prescription_service
+        .check_allergies(
+            req.patient_id,
+            &req.medication_name,
+            req.generic_name.as_deref(),
+            auth_user.user_id,
+            &role_str,
+        )
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to check prescription allergies: {}", e);
+            AppError::Internal(format!("Failed to check prescription allergies: {}", e))
+        })?;
+
+    Ok(Json(alerts))
+}
+
 /// Get prescription by ID
 ///
 /// GET /api/v1/prescriptions/:id
@@ -336,6 +434,373 @@ pub async fn get_prescription(
     Ok(Json(prescription))
 }
 
+/// Print a prescription on the SSN "ricetta" layout
+///
+/// POST /api/v1/prescriptions/:id/ssn-print
+///
+/// The ricetta is filled from the prescription and the patient's fiscal
+/// code; the request only adds exemption code, priority class and notes.
+///
+/// **RBAC**: Requires 'print' permission on 'prescriptions' resource
+/// **Roles**: ADMIN, DOCTOR
+#[cfg(feature = "pdf-export")]
+pub async fn generate_ssn_prescription(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<GenerateSsnPrescriptionRequest>,
+) -> Result<impl IntoResponse> {
+    // Check permissions
+    check_permission(&state, &auth_user.role, "print").await?;
+
+    payload.validate().map_err(|e| {
+        AppError::Validation(format!("Invalid SSN prescription data: {}", e))
+    })?;
+
+    let encryption_key = state
+        .encryption_key
+        .as_ref()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+
+    let role_str = format!("{:?}", auth_user.role).to_uppercase();
+
+    let prescription_service = PrescriptionService::new(state.pool.clone(), encryption_key.clone());
+    let prescription = prescription_service
+        .get_prescription(id, auth_user.user_id, &role_str)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get prescription {}: {}", id, e);
+            AppError::Internal(format!("Failed to get prescription: {}", e))
+        })?
+        .ok_or_else(|| AppError::NotFound(format!("Prescription {} not found", id)))?;
+
+    let packages = prescription.quantity.unwrap_or(1);
+    if !(1..=SSN_MAX_PACKAGES).contains(&packages) {
+        return Err(AppError::Validation(format!(
+            "An SSN prescription allows 1 to {} packages, this prescription has {}",
+            SSN_MAX_PACKAGES, packages
+        )));
+    }
+
+    let storage_path = PathBuf::from(
+        std::env::var("DOCUMENT_STORAGE_PATH").unwrap_or_else(|_| "./documents".to_string()),
+    );
+    let document = DocumentService::new(state.pool.clone(), encryption_key.clone(), storage_path)
+        .with_cache(state.reference_cache.clone())
+        .generate_ssn_prescription(&prescription, &payload, auth_user.user_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to generate SSN prescription for {}: {:?}", id, e);
+            AppError::Internal(format!("Failed to generate SSN prescription: {:#}", e))
+        })?;
+
+    // Log audit entry
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(auth_user.user_id),
+            action: AuditAction::Create,
+            entity_type: EntityType::Prescription,
+            entity_id: Some(id.to_string()),
+            changes: Some(serde_json::json!({
+                "document_id": document.id,
+                "type": "ssn_prescription",
+            })),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+
+    Ok((StatusCode::CREATED, Json(document)))
+}
+
+/// Document service for print batches
+#[cfg(feature = "pdf-export")]
+fn print_batch_document_service(state: &AppState) -> Result<DocumentService> {
+    let encryption_key = state
+        .encryption_key
+        .as_ref()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+
+    let storage_path = PathBuf::from(
+        std::env::var("DOCUMENT_STORAGE_PATH").unwrap_or_else(|_| "./documents".to_string()),
+    );
+
+    Ok(
+        DocumentService::new(state.pool.clone(), encryption_key.clone(), storage_path)
+            .with_cache(state.reference_cache.clone()),
+    )
+}
+
+/// Load the prescriptions selected for a print batch, in print order
+#[cfg(feature = "pdf-export")]
+async fn load_print_batch_prescriptions(
+    prescription_service: &PrescriptionService,
+    req: &CreatePrescriptionPrintBatchRequest,
+    user_id: Uuid,
+    role: &str,
+) -> Result<Vec<PrescriptionResponse>> {
+    if req.today {
+        let today = chrono::Local::now().date_naive();
+        let (mut prescriptions, _) = prescription_service
+            .list_prescriptions(
+                None,
+                None,
+                Some(today),
+                Some(today),
+                MAX_PRINT_BATCH_PRESCRIPTIONS as i64 + 1,
+                0,
+                user_id,
+                role,
+            )
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to list today's prescriptions: {}", e);
+                AppError::Internal(format!("Failed to list prescriptions: {}", e))
+            })?;
+
+        prescriptions.retain(|rx| rx.status != PrescriptionStatus::Cancelled);
+        if prescriptions.len() > MAX_PRINT_BATCH_PRESCRIPTIONS {
+            return Err(AppError::Validation(format!(
+                "More than {} prescriptions today; print them by id",
+                MAX_PRINT_BATCH_PRESCRIPTIONS
+            )));
+        }
+        prescriptions.sort_by_key(|rx| rx.created_at);
+        return Ok(prescriptions);
+    }
+
+    let mut prescriptions = Vec::new();
+    for id in req.resolved_ids() {
+        let prescription = prescription_service
+            .get_prescription(id, user_id, role)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to get prescription {}: {}", id, e);
+                AppError::Internal(format!("Failed to get prescription: {}", e))
+            })?
+            .ok_or_else(|| AppError::NotFound(format!("Prescription {} not found", id)))?;
+        prescriptions.push(prescription);
+    }
+    Ok(prescriptions)
+}
+
+/// Print several prescriptions as one merged PDF
+///
+/// POST /api/v1/prescriptions/print-batches
+///
+/// Selects the listed prescriptions, or with `today` every prescription
+/// written today that is not cancelled, one per page. Batches of up to 10
+/// prescriptions are rendered straight away (201, COMPLETED); larger ones
+/// return 202 in PROCESSING state and clients poll
+/// GET /api/v1/prescriptions/print-batches/:batch_id until it is COMPLETED
+/// or FAILED.
+///
+/// **RBAC**: Requires 'print' permission on 'prescriptions' resource
+/// **Roles**: ADMIN, DOCTOR
+#[cfg(feature = "pdf-export")]
+pub async fn create_prescription_print_batch(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Json(payload): Json<CreatePrescriptionPrintBatchRequest>,
+) -> Result<impl IntoResponse> {
+    // Check permissions
+    check_permission(&state, &auth_user.role, "print").await?;
+
+    payload.validate().map_err(|e| {
+        AppError::Validation(format!("Invalid print batch data: {}", e))
+    })?;
+    if !payload.has_single_selection() {
+        return Err(AppError::Validation(
+            "Provide either prescription_ids or today".to_string(),
+        ));
+    }
+
+    let encryption_key = state
+        .encryption_key
+        .as_ref()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+
+    let role_str = format!("{:?}", auth_user.role).to_uppercase();
+
+    let prescription_service = PrescriptionService::new(state.pool.clone(), encryption_key.clone());
+    let prescriptions =
+        load_print_batch_prescriptions(&prescription_service, &payload, auth_user.user_id, &role_str)
+            .await?;
+
+    if prescriptions.is_empty() {
+        return Err(AppError::Validation("No prescriptions to print".to_string()));
+    }
+    if payload.layout == PrescriptionPrintLayout::Ssn {
+        let over_limit: Vec<String> = prescriptions
+            .iter()
+            .filter(|rx| !(1..=SSN_MAX_PACKAGES).contains(&rx.quantity.unwrap_or(1)))
+            .map(|rx| rx.id.to_string())
+            .collect();
+        if !over_limit.is_empty() {
+            return Err(AppError::Validation(format!(
+                "SSN prescriptions allow 1 to {} packages: {}",
+                SSN_MAX_PACKAGES,
+                over_limit.join(", ")
+            )));
+        }
+    }
+
+    let service = print_batch_document_service(&state)?;
+    let batch = service
+        .create_prescription_print_batch(payload.layout, &prescriptions, auth_user.user_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to create prescription print batch: {:?}", e);
+            AppError::Internal(format!("Failed to create print batch: {:#}", e))
+        })?;
+
+    // Log audit entry
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(auth_user.user_id),
+            action: AuditAction::Create,
+            entity_type: EntityType::Prescription,
+            entity_id: None,
+            changes: Some(serde_json::json!({
+                "batch_id": batch.id,
+                "prescription_ids": batch.prescription_ids,
+                "layout": batch.layout,
+                "type": "print_batch",
+            })),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+
+    let batch_id = batch.id;
+    let layout = payload.layout;
+    let requested_by = auth_user.user_id;
+
+    if prescriptions.len() <= INLINE_PRINT_BATCH_LIMIT {
+        let batch = service
+            .complete_prescription_print_batch(batch_id, layout, &prescriptions, requested_by)
+            .await
+            .map_err(|e| {
+                tracing::error!("Prescription print batch {} failed: {:?}", batch_id, e);
+                AppError::Internal(format!("Failed to print prescriptions: {:#}", e))
+            })?;
+        return Ok((StatusCode::CREATED, Json(batch)));
+    }
+
+    tokio::spawn(with_request_id_scope(request_ctx.request_id, async move {
+        if let Err(e) = service
+            .complete_prescription_print_batch(batch_id, layout, &prescriptions, requested_by)
+            .await
+        {
+            tracing::error!("Prescription print batch {} failed: {:#}", batch_id, e);
+        }
+    }));
+
+    Ok((StatusCode::ACCEPTED, Json(batch)))
+}
+
+/// Get the status of a prescription print batch
+///
+/// GET /api/v1/prescriptions/print-batches/:batch_id
+///
+/// Only the user who requested the batch can see it.
+///
+/// **RBAC**: Requires 'print' permission on 'prescriptions' resource
+#[cfg(feature = "pdf-export")]
+pub async fn get_prescription_print_batch(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(batch_id): Path<Uuid>,
+) -> Result<Json<PrescriptionPrintBatchResponse>> {
+    check_permission(&state, &auth_user.role, "print").await?;
+
+    let batch = print_batch_document_service(&state)?
+        .get_prescription_print_batch(batch_id, auth_user.user_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get prescription print batch {}: {:?}", batch_id, e);
+            AppError::Internal("Failed to get print batch".to_string())
+        })?
+        .ok_or_else(|| AppError::NotFound(format!("Print batch {} not found", batch_id)))?;
+
+    Ok(Json(PrescriptionPrintBatchResponse::from(batch)))
+}
+
+/// Download the merged PDF of a completed print batch
+///
+/// GET /api/v1/prescriptions/print-batches/:batch_id/download
+///
+/// **RBAC**: Requires 'print' permission on 'prescriptions' resource
+#[cfg(feature = "pdf-export")]
+pub async fn download_prescription_print_batch(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(batch_id): Path<Uuid>,
+) -> Result<axum::response::Response> {
+    check_permission(&state, &auth_user.role, "print").await?;
+
+    let batch = print_batch_document_service(&state)?
+        .get_prescription_print_batch(batch_id, auth_user.user_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get prescription print batch {}: {:?}", batch_id, e);
+            AppError::Internal("Failed to get print batch".to_string())
+        })?
+        .ok_or_else(|| AppError::NotFound(format!("Print batch {} not found", batch_id)))?;
+
+    if !batch.is_downloadable() {
+        return Err(AppError::Conflict(format!(
+            "Print batch {} is not ready ({})",
+            batch_id, batch.status
+        )));
+    }
+
+    let file_path = batch.file_path.clone().unwrap_or_default();
+    let file = tokio::fs::File::open(&file_path).await.map_err(|e| {
+        tracing::error!("Failed to open print batch file {}: {}", file_path, e);
+        AppError::Internal("Failed to open print batch file".to_string())
+    })?;
+
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(auth_user.user_id),
+            action: AuditAction::Read,
+            entity_type: EntityType::Prescription,
+            entity_id: None,
+            changes: Some(serde_json::json!({
+                "batch_id": batch_id,
+                "prescription_ids": batch.prescription_ids,
+                "type": "print_batch_download",
+            })),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+
+    let body = Body::from_stream(ReaderStream::new(file));
+    let headers = [
+        (header::CONTENT_TYPE, "application/pdf".to_string()),
+        (
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", batch.download_filename()),
+        ),
+    ];
+
+    Ok((headers, body).into_response())
+}
+
 /// Get all prescriptions for a patient
 ///
 /// GET /api/v1/patients/:patient_id/prescriptions?active_only=true
@@ -776,6 +1241,156 @@ pub async fn complete_prescription(
     Ok(Json(prescription))
 }
 
+/// Record a refill of a prescription
+///
+/// POST /api/v1/prescriptions/:id/refill
+///
+/// Uses up one refill; the expected refill date moves forward from today.
+/// Returns 409 if the prescription is not active or has no refills left.
+///
+/// **RBAC**: Requires 'update' permission on 'prescriptions' resource
+/// **Roles**: ADMIN, DOCTOR
+pub async fn record_prescription_refill(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse> {
+    check_permission(&state, &auth_user.role, "update").await?;
+
+    let encryption_key = state
+        .encryption_key
+        .as_ref()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+
+    let role_str = format!("{:?}", auth_user.role).to_uppercase();
+
+    PrescriptionRefillService::new(state.pool.clone(), encryption_key.clone())
+        .record_refill(id, auth_user.user_id, &role_str)
+        .await?;
+
+    let prescription = PrescriptionService::new(state.pool.clone(), encryption_key.clone())
+        .get_prescription(id, auth_user.user_id, &role_str)
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to get prescription: {}", e)))?
+        .ok_or_else(|| AppError::NotFound(format!("Prescription {} not found", id)))?;
+
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(auth_user.user_id),
+            action: AuditAction::Update,
+            entity_type: EntityType::Prescription,
+            entity_id: Some(id.to_string()),
+            changes: Some(serde_json::json!({
+                "action": "refill",
+                "refills_remaining": prescription.refills_remaining,
+            })),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+
+    Ok(Json(prescription))
+}
+
+/// List active prescriptions overdue for a refill
+///
+/// GET /api/v1/prescriptions/overdue-refills
+///
+/// A prescription is overdue once its supply (estimated from frequency and
+/// quantity) ran out more than `grace_days` ago. Covers every patient;
+/// `patient_id` narrows it to one.
+///
+/// **RBAC**: Requires 'read' permission on 'prescriptions' resource
+/// **Roles**: ADMIN, DOCTOR
+pub async fn list_overdue_refills(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Query(query): Query<OverdueRefillsQuery>,
+) -> Result<Json<Vec<OverdueRefill>>> {
+    check_permission(&state, &auth_user.role, "read").await?;
+
+    query
+        .validate()
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+
+    let encryption_key = state
+        .encryption_key
+        .as_ref()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+
+    let role_str = format!("{:?}", auth_user.role).to_uppercase();
+
+    let overdue = PrescriptionRefillService::new(state.pool.clone(), encryption_key.clone())
+        .list_overdue(&query, auth_user.user_id, &role_str)
+        .await?;
+
+    Ok(Json(overdue))
+}
+
+/// Email refill reminders to patients overdue for a refill
+///
+/// POST /api/v1/prescriptions/overdue-refills/reminders
+///
+/// Queues a REFILL_REMINDER for each overdue prescription (or the listed
+/// ones), skipping patients without email or reminded within
+/// `interval_days`.
+///
+/// **RBAC**: Requires 'remind' permission on 'prescriptions' resource
+/// **Roles**: ADMIN, DOCTOR
+pub async fn send_refill_reminders(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Json(req): Json<SendRefillRemindersRequest>,
+) -> Result<Json<RefillRemindersResponse>> {
+    check_permission(&state, &auth_user.role, "remind").await?;
+
+    req.validate()
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+
+    let encryption_key = state
+        .encryption_key
+        .as_ref()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+    let email_service = state
+        .email_service
+        .clone()
+        .ok_or_else(|| AppError::BadRequest("Email service not configured".to_string()))?;
+
+    let role_str = format!("{:?}", auth_user.role).to_uppercase();
+
+    let notification_service = NotificationService::new(state.pool.clone(), email_service);
+    let result = PrescriptionRefillService::new(state.pool.clone(), encryption_key.clone())
+        .send_reminders(&notification_service, &req, auth_user.user_id, &role_str)
+        .await?;
+
+    if !result.queued.is_empty() {
+        let _ = AuditLog::create(
+            &state.pool,
+            CreateAuditLog {
+                user_id: Some(auth_user.user_id),
+                action: AuditAction::Create,
+                entity_type: EntityType::Notification,
+                entity_id: None,
+                changes: Some(serde_json::json!({
+                    "notification_type": "REFILL_REMINDER",
+                    "prescription_ids": result.queued,
+                })),
+                ip_address: request_ctx.ip_address.clone(),
+                user_agent: request_ctx.user_agent.clone(),
+                request_id: Some(request_ctx.request_id),
+            },
+        )
+        .await;
+    }
+
+    Ok(Json(result))
+}
+
 /// Delete prescription
 ///
 /// DELETE /api/v1/prescriptions/:id
@@ -848,18 +1463,221 @@ pub async fn search_medications(
         .as_ref()
         .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
 
+    let limit = query.limit.unwrap_or(20);
+    let role_str = format!("{:?}", auth_user.role).to_uppercase();
+
+    // The catalog is the same for every prescriber, so it is cached per query
     let prescription_service = PrescriptionService::new(state.pool.clone(), encryption_key.clone());
-    let results = prescription_service
-        .search_medications(&query.query, query.limit.unwrap_or(20))
+    let cache_key = format!("search:{}:{}", query.query.to_lowercase(), limit);
+    let catalog = state
+        .reference_cache
+        .get_or_load(CacheNamespace::Medications, &cache_key, || {
+            prescription_service.search_medications(&query.query, limit)
+        })
         .await
         .map_err(|e| {
             tracing::error!("Failed to search medications: {}", e);
             AppError::Internal(format!("Failed to search medications: {}", e))
         })?;
 
+    // The prescriber's favorites first, then what they prescribe most
+    let results = MedicationFavoriteService::new(state.pool.clone(), encryption_key.clone())
+        .rank_search_results(&query.query, catalog, limit, auth_user.user_id, &role_str)
+        .await?;
+
     Ok(Json(results))
 }
 
+/// List the current user's favorite medications
+///
+/// GET /api/v1/prescriptions/medications/favorites
+///
+/// **RBAC**: Requires 'read' permission on 'prescriptions' resource
+/// **Roles**: ADMIN, DOCTOR
+pub async fn list_medication_favorites(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<Vec<MedicationFavorite>>> {
+    check_permission(&state, &auth_user.role, "read").await?;
+
+    let favorites = medication_favorite_service(&state)?
+        .list_favorites(auth_user.user_id)
+        .await?;
+
+    Ok(Json(favorites))
+}
+
+/// Add a favorite medication with default dosing
+///
+/// POST /api/v1/prescriptions/medications/favorites
+///
+/// Favorites come first in medication search and prefill the prescription
+/// form. Returns 409 if the medication is already a favorite.
+///
+/// **RBAC**: Requires 'create' permission on 'prescriptions' resource
+/// **Roles**: ADMIN, DOCTOR
+pub async fn create_medication_favorite(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Json(req): Json<CreateMedicationFavoriteRequest>,
+) -> Result<(StatusCode, Json<MedicationFavorite>)> {
+    check_permission(&state, &auth_user.role, "create").await?;
+
+    req.validate()
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+
+    let favorite = medication_favorite_service(&state)?
+        .create_favorite(auth_user.user_id, req)
+        .await?;
+
+    audit_medication_favorite(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Create,
+        favorite.id,
+        Some(&favorite.medication_name),
+    )
+    .await;
+
+    Ok((StatusCode::CREATED, Json(favorite)))
+}
+
+/// Update a favorite medication's default dosing or position
+///
+/// PUT /api/v1/prescriptions/medications/favorites/:favorite_id
+///
+/// **RBAC**: Requires 'create' permission on 'prescriptions' resource
+/// **Roles**: ADMIN, DOCTOR
+pub async fn update_medication_favorite(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(favorite_id): Path<Uuid>,
+    Json(req): Json<UpdateMedicationFavoriteRequest>,
+) -> Result<Json<MedicationFavorite>> {
+    check_permission(&state, &auth_user.role, "create").await?;
+
+    req.validate()
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+
+    let favorite = medication_favorite_service(&state)?
+        .update_favorite(auth_user.user_id, favorite_id, req)
+        .await?;
+
+    audit_medication_favorite(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Update,
+        favorite.id,
+        Some(&favorite.medication_name),
+    )
+    .await;
+
+    Ok(Json(favorite))
+}
+
+/// Remove a favorite medication
+///
+/// DELETE /api/v1/prescriptions/medications/favorites/:favorite_id
+///
+/// Favorites are personal, so removing one needs the same permission as
+/// adding it.
+///
+/// **RBAC**: Requires 'create' permission on 'prescriptions' resource
+/// **Roles**: ADMIN, DOCTOR
+pub async fn delete_medication_favorite(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(favorite_id): Path<Uuid>,
+) -> Result<StatusCode> {
+    check_permission(&state, &auth_user.role, "create").await?;
+
+    medication_favorite_service(&state)?
+        .delete_favorite(auth_user.user_id, favorite_id)
+        .await?;
+
+    audit_medication_favorite(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Delete,
+        favorite_id,
+        None,
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Quick-pick list for the prescription form
+///
+/// GET /api/v1/prescriptions/medications/quick-pick?limit=10
+///
+/// The current user's favorites, then the medications they prescribe most
+/// (learned from their own prescriptions).
+///
+/// **RBAC**: Requires 'read' permission on 'prescriptions' resource
+/// **Roles**: ADMIN, DOCTOR
+pub async fn get_medication_quick_pick(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Query(query): Query<QuickPickQuery>,
+) -> Result<Json<QuickPickResponse>> {
+    check_permission(&state, &auth_user.role, "read").await?;
+
+    let role_str = format!("{:?}", auth_user.role).to_uppercase();
+    let limit = query.limit.unwrap_or(10).clamp(1, 50);
+
+    let quick_pick = medication_favorite_service(&state)?
+        .quick_pick(auth_user.user_id, &role_str, limit)
+        .await?;
+
+    Ok(Json(quick_pick))
+}
+
+fn medication_favorite_service(state: &AppState) -> Result<MedicationFavoriteService> {
+    let encryption_key = state
+        .encryption_key
+        .as_ref()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+
+    Ok(MedicationFavoriteService::new(
+        state.pool.clone(),
+        encryption_key.clone(),
+    ))
+}
+
+async fn audit_medication_favorite(
+    state: &AppState,
+    auth_user: &AuthUser,
+    request_ctx: &RequestContext,
+    action: AuditAction,
+    favorite_id: Uuid,
+    medication_name: Option<&str>,
+) {
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(auth_user.user_id),
+            action,
+            entity_type: EntityType::Prescription,
+            entity_id: Some(favorite_id.to_string()),
+            changes: Some(serde_json::json!({
+                "type": "medication_favorite",
+                "name": medication_name,
+            })),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+}
+
 /// Create a custom medication
 ///
 /// POST /api/v1/prescriptions/medications/custom
@@ -910,6 +1728,12 @@ pub async fn create_custom_medication(
             AppError::Internal(format!("Failed to create custom medication: {}", e))
         })?;
 
+    // Cached searches do not include the new medication yet
+    state
+        .reference_cache
+        .invalidate_all(CacheNamespace::Medications)
+        .await;
+
     // Log audit entry
     let _ = AuditLog::create(
         &state.pool,
@@ -0,0 +1,157 @@
+/*!
+ * Rate Limit Handlers
+ *
+ * ADMIN-only view of the rate limits and management of per-user quotas.
+ *
+ * Endpoints:
+ * - GET /api/v1/rate-limits - Default limits and all quotas, with usage
+ * - GET /api/v1/rate-limits/users/:user_id - Limits and usage of a user
+ * - PUT /api/v1/rate-limits/users/:user_id - Set a user's quota
+ * - DELETE /api/v1/rate-limits/users/:user_id - Remove a user's quota
+ */
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    handlers::auth::AppState,
+    models::{
+        AuditAction, AuditLog, AuthUser, CreateAuditLog, EntityType, RateLimitOverview,
+        RequestContext, SetRateLimitQuotaRequest, UserRateLimitStatus, UserRole,
+    },
+    services::RateLimitQuotaService,
+    utils::{AppError, Result},
+};
+
+/// Only administrators may manage rate limits
+fn require_admin(auth_user: &AuthUser) -> Result<()> {
+    if auth_user.role != UserRole::Admin {
+        return Err(AppError::Forbidden(
+            "Only administrators can manage rate limits".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Default limits and all quotas, with usage in the current window
+///
+/// GET /api/v1/rate-limits
+pub async fn get_rate_limits(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<RateLimitOverview>> {
+    require_admin(&auth_user)?;
+
+    let overview = RateLimitQuotaService::new(state.pool.clone())
+        .overview(&state.rate_limiter)
+        .await?;
+
+    Ok(Json(overview))
+}
+
+/// Limits and usage of a user
+///
+/// GET /api/v1/rate-limits/users/:user_id
+pub async fn get_user_rate_limit(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<UserRateLimitStatus>> {
+    require_admin(&auth_user)?;
+
+    let status = RateLimitQuotaService::new(state.pool.clone())
+        .user_status(&state.rate_limiter, user_id)
+        .await?;
+
+    Ok(Json(status))
+}
+
+/// Set a user's quota
+///
+/// PUT /api/v1/rate-limits/users/:user_id
+///
+/// Replaces the defaults for the user; applies at once on this instance and
+/// within a minute on the others.
+pub async fn set_user_rate_limit(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(user_id): Path<Uuid>,
+    Json(req): Json<SetRateLimitQuotaRequest>,
+) -> Result<Json<UserRateLimitStatus>> {
+    require_admin(&auth_user)?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let service = RateLimitQuotaService::new(state.pool.clone());
+    let quota = service.set(user_id, &req, auth_user.user_id).await?;
+    state.rate_limiter.set_quota(user_id, Some(quota.limits()));
+
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(auth_user.user_id),
+            action: AuditAction::Update,
+            entity_type: EntityType::RateLimitQuota,
+            entity_id: Some(user_id.to_string()),
+            changes: Some(serde_json::json!({
+                "requests_per_minute": quota.requests_per_minute,
+                "bulk_requests_per_minute": quota.bulk_requests_per_minute,
+                "reason": quota.reason,
+            })),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+
+    let status = service.user_status(&state.rate_limiter, user_id).await?;
+
+    Ok(Json(status))
+}
+
+/// Remove a user's quota, so the defaults apply again
+///
+/// DELETE /api/v1/rate-limits/users/:user_id
+pub async fn delete_user_rate_limit(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(user_id): Path<Uuid>,
+) -> Result<StatusCode> {
+    require_admin(&auth_user)?;
+
+    let deleted = RateLimitQuotaService::new(state.pool.clone())
+        .delete(user_id)
+        .await?;
+    if !deleted {
+        return Err(AppError::NotFound(
+            "No rate limit quota for this user".to_string(),
+        ));
+    }
+    state.rate_limiter.set_quota(user_id, None);
+
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(auth_user.user_id),
+            action: AuditAction::Delete,
+            entity_type: EntityType::RateLimitQuota,
+            entity_id: Some(user_id.to_string()),
+            changes: None,
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
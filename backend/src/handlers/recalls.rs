@@ -0,0 +1,432 @@
+/*!
+ * Recall Handlers
+ *
+ * Recall and follow-up campaigns. Recall rules find patients overdue for a
+ * lab test or a visit, keep a recall list per rule and send the listed
+ * patients reminders on a schedule. Rules are clinical, so they are limited
+ * to ADMIN and DOCTOR: doctors manage their own rules, administrators
+ * everyone's. Any staff member may record that a patient opted out, since
+ * such requests usually reach the front desk.
+ *
+ * Endpoints:
+ * - GET /api/v1/recalls/rules - List rules
+ * - POST /api/v1/recalls/rules - Create a rule
+ * - GET /api/v1/recalls/rules/:id - Get a rule
+ * - PUT /api/v1/recalls/rules/:id - Update a rule
+ * - DELETE /api/v1/recalls/rules/:id - Delete a rule
+ * - POST /api/v1/recalls/rules/:id/run - Run a rule now
+ * - GET /api/v1/recalls/rules/:id/runs - Latest runs
+ * - GET /api/v1/recalls/rules/:id/entries - Recall list
+ * - GET /api/v1/recalls/rules/:id/outcomes - Campaign outcome
+ * - POST /api/v1/recalls/entries/:id/close - Close a recall list entry
+ * - GET /api/v1/recalls/opt-outs - List opt-outs
+ * - POST /api/v1/recalls/opt-outs - Record an opt-out
+ * - DELETE /api/v1/recalls/opt-outs/:id - Remove an opt-out
+ */
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    handlers::auth::AppState,
+    models::{
+        AuditAction, AuditLog, AuthUser, CreateAuditLog, CreateRecallOptOutRequest,
+        CreateRecallRuleRequest, EntityType, RecallEntryFilter, RecallEntryResponse, RecallOptOut,
+        RecallOptOutFilter, RecallOutcome, RecallRuleResponse, RecallRun, RequestContext,
+        UpdateRecallRuleRequest, UserRole,
+    },
+    services::{NotificationService, RecallService},
+    utils::{AppError, Result},
+};
+
+/// Only administrators and doctors may manage recall rules; returns whether
+/// the user is an administrator
+fn require_recall_access(auth_user: &AuthUser) -> Result<bool> {
+    match auth_user.role {
+        UserRole::Admin => Ok(true),
+        UserRole::Doctor => Ok(false),
+        _ => Err(AppError::Forbidden(
+            "Only administrators and doctors can manage recalls".to_string(),
+        )),
+    }
+}
+
+fn recall_service(state: &AppState) -> Result<RecallService> {
+    let encryption_key = state
+        .encryption_key
+        .clone()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+
+    Ok(RecallService::new(state.pool.clone(), encryption_key))
+}
+
+/// Audit an operation
+async fn audit(
+    state: &AppState,
+    auth_user: &AuthUser,
+    request_ctx: &RequestContext,
+    action: AuditAction,
+    entity_type: EntityType,
+    entity_id: Uuid,
+    changes: Option<serde_json::Value>,
+) {
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(auth_user.user_id),
+            action,
+            entity_type,
+            entity_id: Some(entity_id.to_string()),
+            changes,
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+}
+
+/// Audit log changes of a rule
+fn rule_changes(rule: &RecallRuleResponse) -> serde_json::Value {
+    serde_json::json!({
+        "name": rule.name,
+        "criteria": rule.criteria,
+        "cadence": rule.cadence,
+        "reminder_interval_days": rule.reminder_interval_days,
+        "max_reminders": rule.max_reminders,
+        "is_active": rule.is_active,
+    })
+}
+
+// ==================== Rules ====================
+
+/// List recall rules
+///
+/// GET /api/v1/recalls/rules
+pub async fn list_recall_rules(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<Vec<RecallRuleResponse>>> {
+    let is_admin = require_recall_access(&auth_user)?;
+
+    let rules = recall_service(&state)?
+        .list(auth_user.user_id, is_admin)
+        .await?;
+
+    Ok(Json(rules))
+}
+
+/// Create a recall rule owned by the caller
+///
+/// POST /api/v1/recalls/rules
+pub async fn create_recall_rule(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Json(req): Json<CreateRecallRuleRequest>,
+) -> Result<(StatusCode, Json<RecallRuleResponse>)> {
+    require_recall_access(&auth_user)?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let rule = recall_service(&state)?
+        .create(&req, auth_user.user_id)
+        .await?;
+
+    audit(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Create,
+        EntityType::RecallRule,
+        rule.id,
+        Some(rule_changes(&rule)),
+    )
+    .await;
+
+    Ok((StatusCode::CREATED, Json(rule)))
+}
+
+/// Get a recall rule
+///
+/// GET /api/v1/recalls/rules/:id
+pub async fn get_recall_rule(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(rule_id): Path<Uuid>,
+) -> Result<Json<RecallRuleResponse>> {
+    let is_admin = require_recall_access(&auth_user)?;
+
+    let rule = recall_service(&state)?
+        .get(rule_id, auth_user.user_id, is_admin)
+        .await?;
+
+    Ok(Json(rule))
+}
+
+/// Update a recall rule
+///
+/// PUT /api/v1/recalls/rules/:id
+pub async fn update_recall_rule(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(rule_id): Path<Uuid>,
+    Json(req): Json<UpdateRecallRuleRequest>,
+) -> Result<Json<RecallRuleResponse>> {
+    let is_admin = require_recall_access(&auth_user)?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let rule = recall_service(&state)?
+        .update(rule_id, &req, auth_user.user_id, is_admin)
+        .await?;
+
+    audit(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Update,
+        EntityType::RecallRule,
+        rule.id,
+        Some(rule_changes(&rule)),
+    )
+    .await;
+
+    Ok(Json(rule))
+}
+
+/// Delete a recall rule with its recall list and run history
+///
+/// DELETE /api/v1/recalls/rules/:id
+pub async fn delete_recall_rule(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(rule_id): Path<Uuid>,
+) -> Result<StatusCode> {
+    let is_admin = require_recall_access(&auth_user)?;
+
+    recall_service(&state)?
+        .delete(rule_id, auth_user.user_id, is_admin)
+        .await?;
+
+    audit(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Delete,
+        EntityType::RecallRule,
+        rule_id,
+        None,
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Run a recall rule now, without changing its schedule
+///
+/// POST /api/v1/recalls/rules/:id/run
+///
+/// The run is recorded and audited by the service; a failed run is returned
+/// with its `error`.
+pub async fn run_recall_rule(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(rule_id): Path<Uuid>,
+) -> Result<Json<RecallRun>> {
+    let is_admin = require_recall_access(&auth_user)?;
+
+    let email_service = state
+        .email_service
+        .clone()
+        .ok_or_else(|| AppError::Internal("Email service not configured".to_string()))?;
+    let notification_service = NotificationService::new(state.pool.clone(), email_service);
+
+    let run = recall_service(&state)?
+        .run_now(rule_id, auth_user.user_id, is_admin, &notification_service)
+        .await?;
+
+    Ok(Json(run))
+}
+
+/// Latest runs of a recall rule
+///
+/// GET /api/v1/recalls/rules/:id/runs
+pub async fn list_recall_runs(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(rule_id): Path<Uuid>,
+) -> Result<Json<Vec<RecallRun>>> {
+    let is_admin = require_recall_access(&auth_user)?;
+
+    let runs = recall_service(&state)?
+        .runs(rule_id, auth_user.user_id, is_admin)
+        .await?;
+
+    Ok(Json(runs))
+}
+
+// ==================== Recall Lists ====================
+
+/// Recall list of a rule
+///
+/// GET /api/v1/recalls/rules/:id/entries
+pub async fn list_recall_entries(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(rule_id): Path<Uuid>,
+    Query(filter): Query<RecallEntryFilter>,
+) -> Result<Json<Vec<RecallEntryResponse>>> {
+    let is_admin = require_recall_access(&auth_user)?;
+
+    let entries = recall_service(&state)?
+        .entries(rule_id, &filter, auth_user.user_id, is_admin)
+        .await?;
+
+    audit(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Read,
+        EntityType::RecallRule,
+        rule_id,
+        Some(serde_json::json!({ "entries": entries.len(), "status": filter.status })),
+    )
+    .await;
+
+    Ok(Json(entries))
+}
+
+/// Close an open recall list entry
+///
+/// POST /api/v1/recalls/entries/:id/close
+pub async fn close_recall_entry(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(entry_id): Path<Uuid>,
+) -> Result<Json<RecallEntryResponse>> {
+    let is_admin = require_recall_access(&auth_user)?;
+
+    let entry = recall_service(&state)?
+        .close_entry(entry_id, auth_user.user_id, is_admin)
+        .await?;
+
+    audit(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Update,
+        EntityType::RecallRule,
+        entry.rule_id,
+        Some(serde_json::json!({
+            "entry_id": entry.id,
+            "patient_id": entry.patient_id,
+            "status": entry.status,
+        })),
+    )
+    .await;
+
+    Ok(Json(entry))
+}
+
+/// Outcome of a recall campaign
+///
+/// GET /api/v1/recalls/rules/:id/outcomes
+pub async fn get_recall_outcome(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(rule_id): Path<Uuid>,
+) -> Result<Json<RecallOutcome>> {
+    let is_admin = require_recall_access(&auth_user)?;
+
+    let outcome = recall_service(&state)?
+        .outcome(rule_id, auth_user.user_id, is_admin)
+        .await?;
+
+    Ok(Json(outcome))
+}
+
+// ==================== Opt-outs ====================
+
+/// List recall opt-outs
+///
+/// GET /api/v1/recalls/opt-outs
+pub async fn list_recall_opt_outs(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Query(filter): Query<RecallOptOutFilter>,
+) -> Result<Json<Vec<RecallOptOut>>> {
+    let opt_outs = recall_service(&state)?
+        .list_opt_outs(&filter, auth_user.user_id)
+        .await?;
+
+    Ok(Json(opt_outs))
+}
+
+/// Record that a patient opted out of one recall rule or of every recall
+///
+/// POST /api/v1/recalls/opt-outs
+pub async fn create_recall_opt_out(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Json(req): Json<CreateRecallOptOutRequest>,
+) -> Result<(StatusCode, Json<RecallOptOut>)> {
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let opt_out = recall_service(&state)?
+        .create_opt_out(&req, auth_user.user_id)
+        .await?;
+
+    audit(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Update,
+        EntityType::Patient,
+        opt_out.patient_id,
+        Some(serde_json::json!({ "recall_opt_out": { "rule_id": opt_out.rule_id } })),
+    )
+    .await;
+
+    Ok((StatusCode::CREATED, Json(opt_out)))
+}
+
+/// Remove a recall opt-out
+///
+/// DELETE /api/v1/recalls/opt-outs/:id
+pub async fn delete_recall_opt_out(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(opt_out_id): Path<Uuid>,
+) -> Result<StatusCode> {
+    let opt_out = recall_service(&state)?
+        .delete_opt_out(opt_out_id, auth_user.user_id)
+        .await?;
+
+    audit(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Update,
+        EntityType::Patient,
+        opt_out.patient_id,
+        Some(serde_json::json!({ "recall_opt_out_removed": { "rule_id": opt_out.rule_id } })),
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,370 @@
+/*!
+ * Referral Handlers
+ *
+ * Outgoing specialist referrals and their responses.
+ *
+ * Endpoints:
+ * - GET /api/v1/patients/:id/referrals - List a patient's referrals
+ * - POST /api/v1/patients/:id/referrals - Create a referral
+ * - GET /api/v1/patients/:id/referrals/:referral_id - Get a referral
+ * - PUT /api/v1/patients/:id/referrals/:referral_id - Update a referral
+ * - DELETE /api/v1/patients/:id/referrals/:referral_id - Delete a referral
+ * - POST /api/v1/patients/:id/referrals/:referral_id/letter - Generate the referral letter PDF
+ * - GET /api/v1/referrals/overdue - Referrals still waiting for a response after their due date
+ */
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    handlers::auth::AppState,
+    models::{
+        AuditAction, AuditLog, AuthUser, CreateAuditLog, CreateReferralRequest, EntityType,
+        ListReferralsQuery, OverdueReferral, OverdueReferralsQuery, ReferralResponse,
+        RequestContext, UpdateReferralRequest, UserRole,
+    },
+    services::ReferralService,
+    utils::{AppError, EncryptionKey, Result},
+};
+
+#[cfg(feature = "pdf-export")]
+use crate::{
+    models::{GenerateReferralLetterRequest, ReferralLetterResponse},
+    services::DocumentService,
+};
+#[cfg(feature = "pdf-export")]
+use std::path::PathBuf;
+
+#[cfg(feature = "rbac")]
+use tracing::warn;
+
+/// Check if user has permission to perform action on referrals resource
+#[cfg(feature = "rbac")]
+async fn check_permission(state: &AppState, user_role: &UserRole, action: &str) -> Result<()> {
+    let has_permission = state
+        .enforcer
+        .enforce(user_role, "referrals", action)
+        .await
+        .map_err(|e| {
+            warn!("RBAC enforcement error: {}", e);
+            AppError::Internal("Failed to check permissions".to_string())
+        })?;
+
+    if !has_permission {
+        return Err(AppError::Forbidden(format!(
+            "User does not have permission to {} referrals",
+            action
+        )));
+    }
+
+    Ok(())
+}
+
+/// Fallback for non-RBAC builds - only checks role
+#[cfg(not(feature = "rbac"))]
+async fn check_permission(_state: &AppState, user_role: &UserRole, action: &str) -> Result<()> {
+    let allowed = match action {
+        "read" => matches!(
+            user_role,
+            UserRole::Admin | UserRole::Doctor | UserRole::Nurse
+        ),
+        _ => matches!(user_role, UserRole::Admin | UserRole::Doctor),
+    };
+
+    if !allowed {
+        return Err(AppError::Forbidden("Insufficient permissions".to_string()));
+    }
+    Ok(())
+}
+
+fn service_encryption_key(state: &AppState) -> Result<EncryptionKey> {
+    state
+        .encryption_key
+        .clone()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))
+}
+
+fn referral_service(state: &AppState) -> Result<ReferralService> {
+    Ok(ReferralService::new(
+        state.pool.clone(),
+        service_encryption_key(state)?,
+    ))
+}
+
+/// Audit a referral change; the payload never contains the reason, the
+/// clinical information or the response summary
+async fn audit_referral(
+    state: &AppState,
+    auth_user: &AuthUser,
+    request_ctx: &RequestContext,
+    action: AuditAction,
+    referral_id: Uuid,
+    changes: serde_json::Value,
+) {
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(auth_user.user_id),
+            action,
+            entity_type: EntityType::Referral,
+            entity_id: Some(referral_id.to_string()),
+            changes: Some(changes),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+}
+
+/// List a patient's referrals
+///
+/// GET /api/v1/patients/:id/referrals
+///
+/// **RBAC**: Requires 'read' permission on 'referrals' resource
+pub async fn list_referrals(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(patient_id): Path<Uuid>,
+    Query(query): Query<ListReferralsQuery>,
+) -> Result<Json<Vec<ReferralResponse>>> {
+    check_permission(&state, &auth_user.role, "read").await?;
+
+    let referrals = referral_service(&state)?
+        .list_referrals(patient_id, &query, auth_user.user_id)
+        .await?;
+
+    Ok(Json(referrals))
+}
+
+/// Create a referral
+///
+/// POST /api/v1/patients/:id/referrals
+///
+/// **RBAC**: Requires 'create' permission on 'referrals' resource
+pub async fn create_referral(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(patient_id): Path<Uuid>,
+    Json(req): Json<CreateReferralRequest>,
+) -> Result<(StatusCode, Json<ReferralResponse>)> {
+    check_permission(&state, &auth_user.role, "create").await?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let referral = referral_service(&state)?
+        .create_referral(patient_id, &req, auth_user.user_id)
+        .await?;
+
+    audit_referral(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Create,
+        referral.id,
+        serde_json::json!({
+            "patient_id": patient_id,
+            "visit_id": referral.visit_id,
+            "specialty": referral.specialty,
+            "urgency": referral.urgency,
+            "status": referral.status,
+            "response_due_date": referral.response_due_date,
+        }),
+    )
+    .await;
+
+    Ok((StatusCode::CREATED, Json(referral)))
+}
+
+/// Get a referral
+///
+/// GET /api/v1/patients/:id/referrals/:referral_id
+///
+/// **RBAC**: Requires 'read' permission on 'referrals' resource
+pub async fn get_referral(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path((patient_id, referral_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<ReferralResponse>> {
+    check_permission(&state, &auth_user.role, "read").await?;
+
+    let referral = referral_service(&state)?
+        .get_referral(patient_id, referral_id, auth_user.user_id)
+        .await?;
+
+    Ok(Json(referral))
+}
+
+/// Update a referral
+///
+/// PUT /api/v1/patients/:id/referrals/:referral_id
+///
+/// Also records the referral as sent or the specialist response as
+/// received through `status`.
+///
+/// **RBAC**: Requires 'update' permission on 'referrals' resource
+pub async fn update_referral(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path((patient_id, referral_id)): Path<(Uuid, Uuid)>,
+    Json(req): Json<UpdateReferralRequest>,
+) -> Result<Json<ReferralResponse>> {
+    check_permission(&state, &auth_user.role, "update").await?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let referral = referral_service(&state)?
+        .update_referral(patient_id, referral_id, &req, auth_user.user_id)
+        .await?;
+
+    audit_referral(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Update,
+        referral_id,
+        serde_json::json!({
+            "patient_id": patient_id,
+            "status": req.status,
+            "urgency": req.urgency,
+            "response_due_date": req.response_due_date,
+            "response_attachment_id": req.response_attachment_id,
+            "response_changed": req.response_summary.is_some(),
+        }),
+    )
+    .await;
+
+    Ok(Json(referral))
+}
+
+/// Delete a referral
+///
+/// DELETE /api/v1/patients/:id/referrals/:referral_id
+///
+/// The referral is marked deleted and kept with the medical record.
+///
+/// **RBAC**: Requires 'delete' permission on 'referrals' resource
+pub async fn delete_referral(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path((patient_id, referral_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode> {
+    check_permission(&state, &auth_user.role, "delete").await?;
+
+    referral_service(&state)?
+        .delete_referral(patient_id, referral_id, auth_user.user_id)
+        .await?;
+
+    audit_referral(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Delete,
+        referral_id,
+        serde_json::json!({ "patient_id": patient_id }),
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// List referrals still waiting for a response after their due date
+///
+/// GET /api/v1/referrals/overdue
+///
+/// Covers every patient; `referred_by` narrows it to one doctor's referrals.
+///
+/// **RBAC**: Requires 'read' permission on 'referrals' resource
+pub async fn list_overdue_referrals(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Query(query): Query<OverdueReferralsQuery>,
+) -> Result<Json<Vec<OverdueReferral>>> {
+    check_permission(&state, &auth_user.role, "read").await?;
+
+    let referrals = referral_service(&state)?
+        .list_overdue(&query, auth_user.user_id)
+        .await?;
+
+    Ok(Json(referrals))
+}
+
+/// Generate the referral letter PDF
+///
+/// POST /api/v1/patients/:id/referrals/:referral_id/letter
+///
+/// Renders a REFERRAL_LETTER template with the referral's specialty, reason
+/// and clinical information and links the document to the referral.
+///
+/// **RBAC**: Requires 'letter' permission on 'referrals' resource
+#[cfg(feature = "pdf-export")]
+pub async fn generate_referral_letter(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path((patient_id, referral_id)): Path<(Uuid, Uuid)>,
+    Json(req): Json<GenerateReferralLetterRequest>,
+) -> Result<(StatusCode, Json<ReferralLetterResponse>)> {
+    check_permission(&state, &auth_user.role, "letter").await?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let service = referral_service(&state)?;
+    let referral = service
+        .prepare_letter(patient_id, referral_id, auth_user.user_id)
+        .await?;
+
+    let storage_path = PathBuf::from(
+        std::env::var("DOCUMENT_STORAGE_PATH").unwrap_or_else(|_| "./documents".to_string()),
+    );
+    let document = DocumentService::new(
+        state.pool.clone(),
+        service_encryption_key(&state)?,
+        storage_path,
+    )
+    .with_cache(state.reference_cache.clone())
+    .generate_referral_letter(&referral, &req, auth_user.user_id)
+    .await
+    .map_err(|e| {
+        tracing::error!(
+            "Failed to generate letter for referral {}: {:?}",
+            referral_id,
+            e
+        );
+        AppError::Internal(format!("Failed to generate referral letter: {:#}", e))
+    })?;
+
+    let referral = service
+        .link_letter(patient_id, referral_id, document.id, auth_user.user_id)
+        .await?;
+
+    audit_referral(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Create,
+        referral_id,
+        serde_json::json!({
+            "patient_id": patient_id,
+            "document_id": document.id,
+            "type": "referral_letter",
+        }),
+    )
+    .await;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(ReferralLetterResponse { referral, document }),
+    ))
+}
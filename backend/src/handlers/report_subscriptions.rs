@@ -0,0 +1,238 @@
+/*!
+ * Report Subscription Handlers
+ *
+ * Scheduled report emails. Users who can read reports (ADMIN, DOCTOR)
+ * manage their own subscriptions; administrators see and manage everyone's.
+ *
+ * Endpoints:
+ * - GET /api/v1/reports/subscriptions - List subscriptions
+ * - POST /api/v1/reports/subscriptions - Create a subscription
+ * - GET /api/v1/reports/subscriptions/:id - Get a subscription
+ * - PUT /api/v1/reports/subscriptions/:id - Update a subscription
+ * - DELETE /api/v1/reports/subscriptions/:id - Delete a subscription
+ * - POST /api/v1/reports/subscriptions/:id/send - Deliver now
+ */
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    handlers::auth::AppState,
+    models::{
+        AuditAction, AuditLog, AuthUser, CreateAuditLog, CreateReportSubscriptionRequest,
+        EntityType, ReportSubscriptionResponse, RequestContext, UpdateReportSubscriptionRequest,
+        UserRole,
+    },
+    services::ReportSubscriptionService,
+    utils::{AppError, Result},
+};
+
+/// Only users who can read reports may subscribe to them; returns whether
+/// the user is an administrator
+fn require_report_reader(auth_user: &AuthUser) -> Result<bool> {
+    match auth_user.role {
+        UserRole::Admin => Ok(true),
+        UserRole::Doctor => Ok(false),
+        _ => Err(AppError::Forbidden(
+            "Only administrators and doctors can subscribe to reports".to_string(),
+        )),
+    }
+}
+
+/// Audit a change of a subscription
+async fn audit(
+    state: &AppState,
+    auth_user: &AuthUser,
+    request_ctx: &RequestContext,
+    action: AuditAction,
+    subscription_id: Uuid,
+    changes: Option<serde_json::Value>,
+) {
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(auth_user.user_id),
+            action,
+            entity_type: EntityType::ReportSubscription,
+            entity_id: Some(subscription_id.to_string()),
+            changes,
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+}
+
+/// Audit log changes of a subscription
+fn subscription_changes(subscription: &ReportSubscriptionResponse) -> serde_json::Value {
+    serde_json::json!({
+        "name": subscription.name,
+        "report_type": subscription.report_type,
+        "format": subscription.format,
+        "cadence": subscription.cadence,
+        "recipients": subscription.recipients,
+        "is_active": subscription.is_active,
+    })
+}
+
+/// List report subscriptions
+///
+/// GET /api/v1/reports/subscriptions
+pub async fn list_report_subscriptions(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<Vec<ReportSubscriptionResponse>>> {
+    let is_admin = require_report_reader(&auth_user)?;
+
+    let subscriptions = ReportSubscriptionService::new(state.pool.clone())
+        .list(auth_user.user_id, is_admin)
+        .await?;
+
+    Ok(Json(subscriptions))
+}
+
+/// Create a report subscription owned by the caller
+///
+/// POST /api/v1/reports/subscriptions
+pub async fn create_report_subscription(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Json(req): Json<CreateReportSubscriptionRequest>,
+) -> Result<(StatusCode, Json<ReportSubscriptionResponse>)> {
+    require_report_reader(&auth_user)?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let subscription = ReportSubscriptionService::new(state.pool.clone())
+        .create(&req, auth_user.user_id)
+        .await?;
+
+    audit(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Create,
+        subscription.id,
+        Some(subscription_changes(&subscription)),
+    )
+    .await;
+
+    Ok((StatusCode::CREATED, Json(subscription)))
+}
+
+/// Get a report subscription
+///
+/// GET /api/v1/reports/subscriptions/:id
+pub async fn get_report_subscription(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(subscription_id): Path<Uuid>,
+) -> Result<Json<ReportSubscriptionResponse>> {
+    let is_admin = require_report_reader(&auth_user)?;
+
+    let subscription = ReportSubscriptionService::new(state.pool.clone())
+        .get(subscription_id, auth_user.user_id, is_admin)
+        .await?;
+
+    Ok(Json(subscription))
+}
+
+/// Update a report subscription
+///
+/// PUT /api/v1/reports/subscriptions/:id
+pub async fn update_report_subscription(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(subscription_id): Path<Uuid>,
+    Json(req): Json<UpdateReportSubscriptionRequest>,
+) -> Result<Json<ReportSubscriptionResponse>> {
+    let is_admin = require_report_reader(&auth_user)?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let subscription = ReportSubscriptionService::new(state.pool.clone())
+        .update(subscription_id, &req, auth_user.user_id, is_admin)
+        .await?;
+
+    audit(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Update,
+        subscription.id,
+        Some(subscription_changes(&subscription)),
+    )
+    .await;
+
+    Ok(Json(subscription))
+}
+
+/// Delete a report subscription
+///
+/// DELETE /api/v1/reports/subscriptions/:id
+pub async fn delete_report_subscription(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(subscription_id): Path<Uuid>,
+) -> Result<StatusCode> {
+    let is_admin = require_report_reader(&auth_user)?;
+
+    ReportSubscriptionService::new(state.pool.clone())
+        .delete(subscription_id, auth_user.user_id, is_admin)
+        .await?;
+
+    audit(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Delete,
+        subscription_id,
+        None,
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Deliver a report subscription now
+///
+/// POST /api/v1/reports/subscriptions/:id/send
+///
+/// Sends the report of the last complete period without changing the
+/// schedule. The outcome is in `last_status` / `last_error`; the delivery is
+/// audited by the service.
+pub async fn send_report_subscription(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(subscription_id): Path<Uuid>,
+) -> Result<Json<ReportSubscriptionResponse>> {
+    let is_admin = require_report_reader(&auth_user)?;
+
+    let email_service = state
+        .email_service
+        .as_ref()
+        .ok_or_else(|| AppError::BadRequest("Email service is not configured".to_string()))?;
+
+    let subscription = ReportSubscriptionService::new(state.pool.clone())
+        .send_now(
+            subscription_id,
+            auth_user.user_id,
+            is_admin,
+            email_service,
+            state.encryption_key.as_ref(),
+        )
+        .await?;
+
+    Ok(Json(subscription))
+}
@@ -7,8 +7,10 @@
  * - Diagnosis trends
  * - Provider productivity
  * - Revenue tracking
+ * - GP quality indicators (regional CSV layout)
  * - Dashboard overview
  * - Report export (JSON, CSV, PDF, Excel)
+ * - Anonymized research dataset export
  */
 
 use axum::{
@@ -23,12 +25,15 @@ use uuid::Uuid;
 use crate::{
     handlers::auth::AppState,
     models::{
-        AppointmentReportFilter, DiagnosisReportFilter, ExportReportRequest,
-        PatientReportFilter, ProductivityReportFilter, ReportType, RevenueReportFilter, UserRole,
+        AppointmentReportFilter, AuditAction, AuditLog, CreateAuditLog, DiagnosisReportFilter,
+        EntityType, ExportFormat, ExportReportRequest, PatientReportFilter, ProductivityReportFilter,
+        QualityIndicatorFilter, QualityIndicatorReport, ReportDateRange,
+        RequestContext, ResearchExportRequest, DEFAULT_RESEARCH_EXPORT_MIN_K, RevenueReportFilter, UserRole,
     },
-    services::{ReportExportService, ReportService},
+    services::{QualityIndicatorService, ReportExportService, ReportService},
     utils::{AppError, Result},
 };
+use validator::Validate;
 
 #[cfg(feature = "rbac")]
 use tracing::warn;
@@ -64,9 +69,15 @@ async fn check_permission(
 async fn check_permission(
     _state: &AppState,
     user_role: &UserRole,
-    _action: &str,
+    action: &str,
 ) -> Result<()> {
     // Simple role-based check without Casbin
+    // Only ADMIN can export research datasets
+    if action == "export" && !matches!(user_role, UserRole::Admin) {
+        return Err(AppError::Forbidden(
+            "Insufficient permissions to export research datasets".to_string(),
+        ));
+    }
     // Both ADMIN and DOCTOR can read reports
     if !matches!(user_role, UserRole::Admin | UserRole::Doctor) {
         return Err(AppError::Forbidden(
@@ -114,6 +125,46 @@ pub async fn get_appointment_report(
     Ok((StatusCode::OK, Json(report)))
 }
 
+/// Get appointment heatmap
+///
+/// GET /api/v1/reports/appointments/heatmap
+///
+/// Returns weekday x hour matrices of booking and no-show density.
+///
+/// Query parameters:
+/// - `start_date`: Start date filter (YYYY-MM-DD, default: 30 days ago)
+/// - `end_date`: End date filter (YYYY-MM-DD, default: today)
+/// - `provider_id`: Filter by provider UUID
+///
+/// **RBAC**: Requires 'read' permission on 'reports' resource
+/// **Roles**: ADMIN, DOCTOR
+pub async fn get_appointment_heatmap(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(user_role): Extension<UserRole>,
+    Query(filter): Query<AppointmentReportFilter>,
+) -> Result<impl IntoResponse> {
+    // Check permissions
+    check_permission(&state, &user_role, "read").await?;
+
+    if let (Some(start), Some(end)) = (filter.start_date, filter.end_date) {
+        if start > end {
+            return Err(AppError::BadRequest(
+                "start_date must be before or equal to end_date".to_string(),
+            ));
+        }
+    }
+
+    let report_service = ReportService::new(state.pool.clone());
+
+    let report = report_service
+        .get_appointment_heatmap(filter, user_id)
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to generate appointment heatmap: {}", e)))?;
+
+    Ok((StatusCode::OK, Json(report)))
+}
+
 /// Get patient statistics report
 ///
 /// GET /api/v1/reports/patients
@@ -144,7 +195,7 @@ pub async fn get_patient_report(
 
     // Generate report
     let report = report_service
-        .get_patient_statistics(filter, user_id)
+        .get_patient_statistics(filter, state.encryption_key.as_ref(), user_id)
         .await
         .map_err(|e| AppError::Internal(format!("Failed to generate patient report: {}", e)))?;
 
@@ -352,102 +403,272 @@ pub async fn export_report(
     // Check permissions
     check_permission(&state, &user_role, "read").await?;
 
-    // Create services
-    let report_service = ReportService::new(state.pool.clone());
-    let export_service = ReportExportService::new();
+    // Generate and export the requested report
+    let export_response = ReportExportService::new()
+        .generate(
+            &ReportService::new(state.pool.clone()),
+            &req,
+            state.encryption_key.as_ref(),
+            user_id,
+        )
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to export report: {:#}", e)))?;
 
-    // Generate and export the appropriate report based on type
-    let export_response = match req.report_type {
-        ReportType::AppointmentUtilization => {
-            let filter = AppointmentReportFilter {
-                start_date: req.start_date,
-                end_date: req.end_date,
-                provider_id: req.provider_id,
-            };
-            let report = report_service
-                .get_appointment_utilization(filter, user_id)
-                .await
-                .map_err(|e| AppError::Internal(format!("Failed to generate report: {}", e)))?;
-            export_service
-                .export_appointment_report(&report, &req.format)
-                .map_err(|e| AppError::Internal(format!("Failed to export report: {}", e)))?
-        }
-        ReportType::PatientStatistics => {
-            let filter = PatientReportFilter {
-                start_date: req.start_date,
-                end_date: req.end_date,
-            };
-            let report = report_service
-                .get_patient_statistics(filter, user_id)
-                .await
-                .map_err(|e| AppError::Internal(format!("Failed to generate report: {}", e)))?;
-            export_service
-                .export_patient_report(&report, &req.format)
-                .map_err(|e| AppError::Internal(format!("Failed to export report: {}", e)))?
-        }
-        ReportType::DiagnosisTrends => {
-            let filter = DiagnosisReportFilter {
-                start_date: req.start_date,
-                end_date: req.end_date,
-                provider_id: req.provider_id,
-                limit: None,
-            };
-            let report = report_service
-                .get_diagnosis_trends(filter, user_id)
-                .await
-                .map_err(|e| AppError::Internal(format!("Failed to generate report: {}", e)))?;
-            export_service
-                .export_diagnosis_report(&report, &req.format)
-                .map_err(|e| AppError::Internal(format!("Failed to export report: {}", e)))?
-        }
-        ReportType::ProviderProductivity => {
-            let filter = ProductivityReportFilter {
-                start_date: req.start_date,
-                end_date: req.end_date,
-                provider_id: req.provider_id,
-            };
-            let report = report_service
-                .get_provider_productivity(filter, user_id)
-                .await
-                .map_err(|e| AppError::Internal(format!("Failed to generate report: {}", e)))?;
-            export_service
-                .export_productivity_report(&report, &req.format)
-                .map_err(|e| AppError::Internal(format!("Failed to export report: {}", e)))?
-        }
-        ReportType::Revenue => {
-            let filter = RevenueReportFilter {
-                start_date: req.start_date,
-                end_date: req.end_date,
-                provider_id: req.provider_id,
-            };
-            let report = report_service
-                .get_revenue_report(filter, user_id)
-                .await
-                .map_err(|e| AppError::Internal(format!("Failed to generate report: {}", e)))?;
-            export_service
-                .export_revenue_report(&report, &req.format)
-                .map_err(|e| AppError::Internal(format!("Failed to export report: {}", e)))?
-        }
-        ReportType::Dashboard => {
-            // Dashboard doesn't support export to other formats - return JSON only
-            let report = report_service
-                .get_dashboard_report(user_id)
-                .await
-                .map_err(|e| AppError::Internal(format!("Failed to generate report: {}", e)))?;
-            let data = serde_json::to_vec_pretty(&report)
-                .map_err(|e| AppError::Internal(format!("Failed to serialize report: {}", e)))?;
-            crate::services::ExportResponse {
-                data,
-                content_type: "application/json".to_string(),
-                filename: format!(
-                    "dashboard_report_{}.json",
-                    chrono::Utc::now().format("%Y%m%d_%H%M%S")
-                ),
-            }
+    // Build response with appropriate headers for file download
+    let response = Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, export_response.content_type)
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", export_response.filename),
+        )
+        .body(Body::from(export_response.data))
+        .map_err(|e| AppError::Internal(format!("Failed to build response: {}", e)))?;
+
+    Ok(response)
+}
+
+/// Compute the GP quality indicators and write the audit log entry
+async fn generate_quality_indicators(
+    state: &AppState,
+    user_id: Uuid,
+    request_ctx: &RequestContext,
+    filter: QualityIndicatorFilter,
+    action: AuditAction,
+) -> Result<QualityIndicatorReport> {
+    if let (Some(start), Some(end)) = (filter.start_date, filter.end_date) {
+        if start > end {
+            return Err(AppError::BadRequest(
+                "start_date must be before or equal to end_date".to_string(),
+            ));
         }
+    }
+
+    let encryption_key = state
+        .encryption_key
+        .as_ref()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+
+    let service = QualityIndicatorService::new(state.pool.clone(), encryption_key.clone());
+    let report = service.generate(filter, user_id).await.map_err(|e| {
+        AppError::Internal(format!("Failed to generate quality indicators: {}", e))
+    })?;
+
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(user_id),
+            action,
+            entity_type: EntityType::Report,
+            entity_id: Some(report.run_id.to_string()),
+            changes: Some(serde_json::json!({
+                "report": "quality_indicators",
+                "period_start": report.date_range.start_date,
+                "period_end": report.date_range.end_date,
+                "inputs": report.inputs,
+            })),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+
+    Ok(report)
+}
+
+/// Get GP quality indicators
+///
+/// GET /api/v1/reports/quality-indicators
+///
+/// Computes the regional contract governance indicators (hypertension BP
+/// recording and control, diabetic follow-up, influenza vaccination coverage).
+/// Each run is stored in `quality_indicator_runs` together with its inputs.
+///
+/// Query parameters:
+/// - `start_date`: Period start (YYYY-MM-DD, default: 12 months before end)
+/// - `end_date`: Period end (YYYY-MM-DD, default: today)
+///
+/// **RBAC**: Requires 'read' permission on 'reports' resource
+/// **Roles**: ADMIN, DOCTOR
+pub async fn get_quality_indicators(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(user_role): Extension<UserRole>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Query(filter): Query<QualityIndicatorFilter>,
+) -> Result<impl IntoResponse> {
+    // Check permissions
+    check_permission(&state, &user_role, "read").await?;
+
+    let report =
+        generate_quality_indicators(&state, user_id, &request_ctx, filter, AuditAction::Read)
+            .await?;
+
+    Ok((StatusCode::OK, Json(report)))
+}
+
+/// Export GP quality indicators in the regional CSV layout
+///
+/// GET /api/v1/reports/quality-indicators/export
+///
+/// Same query parameters as `get_quality_indicators`. Returns a
+/// semicolon-separated CSV attachment; the run ID is returned in the
+/// `X-Report-Run-Id` header.
+///
+/// **RBAC**: Requires 'read' permission on 'reports' resource
+/// **Roles**: ADMIN, DOCTOR
+pub async fn export_quality_indicators(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(user_role): Extension<UserRole>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Query(filter): Query<QualityIndicatorFilter>,
+) -> Result<Response> {
+    // Check permissions
+    check_permission(&state, &user_role, "read").await?;
+
+    let report =
+        generate_quality_indicators(&state, user_id, &request_ctx, filter, AuditAction::Export)
+            .await?;
+
+    let export_response = ReportExportService::new()
+        .export_quality_indicators_csv(&report)
+        .map_err(|e| AppError::Internal(format!("Failed to export report: {}", e)))?;
+
+    let response = Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, export_response.content_type)
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", export_response.filename),
+        )
+        .header("X-Report-Run-Id", report.run_id.to_string())
+        .body(Body::from(export_response.data))
+        .map_err(|e| AppError::Internal(format!("Failed to build response: {}", e)))?;
+
+    Ok(response)
+}
+
+/// Export an anonymized research dataset
+///
+/// POST /api/v1/reports/research-export
+///
+/// Request body:
+/// ```json
+/// {
+///   "dataset": "diagnoses",
+///   "format": "csv",
+///   "start_date": "2025-01-01",
+///   "end_date": "2025-12-31",
+///   "profile": { "k": 10, "age_band_years": 10, "date_precision": "quarter" }
+/// }
+/// ```
+///
+/// The dataset holds no direct identifiers and is k-anonymized according to
+/// the profile; `k` may not be below the `research_export_min_k` setting.
+/// Summary counts are returned in the `X-Anonymization-Summary` header.
+///
+/// **RBAC**: Requires 'export' permission on 'reports' resource
+/// **Roles**: ADMIN
+pub async fn export_research_dataset(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(user_role): Extension<UserRole>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Json(req): Json<ResearchExportRequest>,
+) -> Result<Response> {
+    // Check permissions
+    check_permission(&state, &user_role, "export").await?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    if req.format == ExportFormat::Pdf {
+        return Err(AppError::BadRequest(
+            "Research datasets can be exported as csv, excel or json".to_string(),
+        ));
+    }
+
+    let min_k = match state
+        .settings_service
+        .get_setting("research_export_min_k")
+        .await
+    {
+        Ok(Some(setting)) => setting
+            .setting_value
+            .as_i64()
+            .unwrap_or(DEFAULT_RESEARCH_EXPORT_MIN_K),
+        _ => DEFAULT_RESEARCH_EXPORT_MIN_K,
     };
+    if req.profile.k < min_k {
+        return Err(AppError::Validation(format!(
+            "k must be at least {} (research_export_min_k)",
+            min_k
+        )));
+    }
+
+    let end_date = req.end_date.unwrap_or_else(|| chrono::Utc::now().date_naive());
+    let start_date = req
+        .start_date
+        .unwrap_or_else(|| end_date - chrono::Months::new(12));
+    if start_date > end_date {
+        return Err(AppError::BadRequest(
+            "start_date must be before or equal to end_date".to_string(),
+        ));
+    }
+    let period = ReportDateRange {
+        start_date,
+        end_date,
+    };
+
+    let encryption_key = state
+        .encryption_key
+        .as_ref()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+
+    let (records, unreadable) = ReportService::new(state.pool.clone())
+        .get_research_records(req.dataset, &period, &req.profile, encryption_key, user_id)
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to load research dataset: {}", e)))?;
+
+    let export_service = ReportExportService::new();
+    let dataset = export_service.anonymize_research_records(
+        req.dataset,
+        records,
+        &req.profile,
+        period,
+        unreadable,
+    );
+    let export_response = export_service
+        .export_research_dataset(&dataset, &req.format)
+        .map_err(|e| AppError::Internal(format!("Failed to export research dataset: {}", e)))?;
+
+    let summary = serde_json::to_value(&dataset.summary)
+        .map_err(|e| AppError::Internal(format!("Failed to serialize summary: {}", e)))?;
+
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(user_id),
+            action: AuditAction::Export,
+            entity_type: EntityType::Report,
+            entity_id: None,
+            changes: Some(serde_json::json!({
+                "report": "research_dataset",
+                "dataset": dataset.dataset,
+                "period_start": dataset.date_range.start_date,
+                "period_end": dataset.date_range.end_date,
+                "profile": dataset.profile,
+                "summary": summary,
+            })),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
 
-    // Build response with appropriate headers for file download
     let response = Response::builder()
         .status(StatusCode::OK)
         .header(header::CONTENT_TYPE, export_response.content_type)
@@ -455,6 +676,7 @@ pub async fn export_report(
             header::CONTENT_DISPOSITION,
             format!("attachment; filename=\"{}\"", export_response.filename),
         )
+        .header("X-Anonymization-Summary", summary.to_string())
         .body(Body::from(export_response.data))
         .map_err(|e| AppError::Internal(format!("Failed to build response: {}", e)))?;
 
@@ -0,0 +1,147 @@
+/*!
+ * Resource Handlers
+ *
+ * Rooms and equipment appointments can reserve. Reservations are made with
+ * `resource_ids` when creating or changing an appointment; the availability
+ * check takes them too.
+ *
+ * Endpoints:
+ * - GET /api/v1/appointments/resources - List resources
+ * - POST /api/v1/appointments/resources - Add a resource
+ * - PUT /api/v1/appointments/resources/:id - Change or deactivate a resource
+ */
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    handlers::{appointments::check_permission, auth::AppState},
+    models::{
+        AuditAction, AuditLog, CreateAuditLog, CreateResourceRequest, EntityType, RequestContext,
+        Resource, ResourceQuery, UpdateResourceRequest, UserRole,
+    },
+    services::ResourceService,
+    utils::{AppError, Result},
+};
+
+/// Audit a change to a resource
+async fn audit(
+    state: &AppState,
+    user_id: Uuid,
+    request_ctx: &RequestContext,
+    action: AuditAction,
+    resource: &Resource,
+) {
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(user_id),
+            action,
+            entity_type: EntityType::Resource,
+            entity_id: Some(resource.id.to_string()),
+            changes: Some(serde_json::json!({
+                "name": resource.name,
+                "resource_type": resource.resource_type,
+                "is_active": resource.is_active,
+            })),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+}
+
+/// List resources
+///
+/// GET /api/v1/appointments/resources
+///
+/// Active resources by name; `include_inactive=true` adds the deactivated
+/// ones.
+pub async fn list_resources(
+    State(state): State<AppState>,
+    Extension(user_role): Extension<UserRole>,
+    Query(query): Query<ResourceQuery>,
+) -> Result<Json<Vec<Resource>>> {
+    check_permission(&state, &user_role, "read").await?;
+
+    let resources = ResourceService::new(state.pool.clone())
+        .list_resources(&query)
+        .await?;
+
+    Ok(Json(resources))
+}
+
+/// Add a resource
+///
+/// POST /api/v1/appointments/resources
+///
+/// **RBAC**: Requires 'manage_resources' permission on 'appointments' resource
+pub async fn create_resource(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(user_role): Extension<UserRole>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Json(request): Json<CreateResourceRequest>,
+) -> Result<(StatusCode, Json<Resource>)> {
+    check_permission(&state, &user_role, "manage_resources").await?;
+
+    request
+        .validate()
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+
+    let resource = ResourceService::new(state.pool.clone())
+        .create_resource(&request, user_id)
+        .await?;
+
+    audit(
+        &state,
+        user_id,
+        &request_ctx,
+        AuditAction::Create,
+        &resource,
+    )
+    .await;
+
+    Ok((StatusCode::CREATED, Json(resource)))
+}
+
+/// Change or deactivate a resource
+///
+/// PUT /api/v1/appointments/resources/:id
+///
+/// **RBAC**: Requires 'manage_resources' permission on 'appointments' resource
+pub async fn update_resource(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(user_role): Extension<UserRole>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(id): Path<Uuid>,
+    Json(request): Json<UpdateResourceRequest>,
+) -> Result<Json<Resource>> {
+    check_permission(&state, &user_role, "manage_resources").await?;
+
+    request
+        .validate()
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+
+    let resource = ResourceService::new(state.pool.clone())
+        .update_resource(id, &request, user_id)
+        .await?;
+
+    audit(
+        &state,
+        user_id,
+        &request_ctx,
+        AuditAction::Update,
+        &resource,
+    )
+    .await;
+
+    Ok(Json(resource))
+}
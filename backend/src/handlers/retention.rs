@@ -0,0 +1,134 @@
+/*!
+ * Data Retention Handlers
+ *
+ * ADMIN-only management of retention rules and their runs.
+ *
+ * Endpoints:
+ * - GET /api/v1/retention-rules - List rules
+ * - PUT /api/v1/retention-rules/:id - Update a rule
+ * - POST /api/v1/retention-rules/run - Run rules now (dry run by default)
+ * - GET /api/v1/retention-rules/runs - Run history
+ */
+
+use axum::{
+    extract::{Path, Query, State},
+    Extension, Json,
+};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    handlers::auth::AppState,
+    models::{
+        AuditAction, AuditLog, AuthUser, CreateAuditLog, EntityType, ListRetentionRunsQuery,
+        RequestContext, RetentionRuleResponse, RetentionRun, RunRetentionRequest,
+        UpdateRetentionRuleRequest, UserRole,
+    },
+    services::RetentionService,
+    utils::{AppError, Result},
+};
+
+/// Only administrators may manage retention
+fn require_admin(auth_user: &AuthUser) -> Result<()> {
+    if auth_user.role != UserRole::Admin {
+        return Err(AppError::Forbidden(
+            "Only administrators can manage data retention".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// List retention rules
+///
+/// GET /api/v1/retention-rules
+pub async fn list_retention_rules(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<Vec<RetentionRuleResponse>>> {
+    require_admin(&auth_user)?;
+
+    let rules = RetentionService::new(state.pool.clone())
+        .list_rules()
+        .await?;
+
+    Ok(Json(rules))
+}
+
+/// Update a retention rule
+///
+/// PUT /api/v1/retention-rules/:id
+pub async fn update_retention_rule(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(rule_id): Path<Uuid>,
+    Json(req): Json<UpdateRetentionRuleRequest>,
+) -> Result<Json<RetentionRuleResponse>> {
+    require_admin(&auth_user)?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let rule = RetentionService::new(state.pool.clone())
+        .update_rule(rule_id, &req, auth_user.user_id)
+        .await?;
+
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(auth_user.user_id),
+            action: AuditAction::Update,
+            entity_type: EntityType::RetentionRule,
+            entity_id: Some(rule_id.to_string()),
+            changes: Some(serde_json::json!({
+                "entity_type": rule.entity_type,
+                "retention_days": rule.retention_days,
+                "is_enabled": rule.is_enabled,
+                "dry_run": rule.dry_run,
+            })),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+
+    Ok(Json(rule))
+}
+
+/// Run retention rules now
+///
+/// POST /api/v1/retention-rules/run
+///
+/// Dry run unless `dry_run` is false. A dry run covers every rule; a real
+/// run covers enabled rules only. Each rule run is audited by the service.
+pub async fn run_retention(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Json(req): Json<RunRetentionRequest>,
+) -> Result<Json<Vec<RetentionRun>>> {
+    require_admin(&auth_user)?;
+
+    let runs = RetentionService::new(state.pool.clone())
+        .run_now(req.dry_run, req.entity_type, auth_user.user_id)
+        .await?;
+
+    Ok(Json(runs))
+}
+
+/// List retention runs, newest first
+///
+/// GET /api/v1/retention-rules/runs
+pub async fn list_retention_runs(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Query(query): Query<ListRetentionRunsQuery>,
+) -> Result<Json<Vec<RetentionRun>>> {
+    require_admin(&auth_user)?;
+
+    let runs = RetentionService::new(state.pool.clone())
+        .list_runs(&query)
+        .await?;
+
+    Ok(Json(runs))
+}
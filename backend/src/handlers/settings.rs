@@ -14,11 +14,17 @@ use serde::Deserialize;
 use uuid::Uuid;
 
 use crate::handlers::auth::AppState;
+use crate::middleware::ip_allowlist::{
+    is_allowed, parse_client_ip, parse_ranges, ADMIN_IP_ALLOWLIST_SETTING,
+};
 use crate::models::system_setting::{
     BulkUpdateSettingsRequest, ListSettingGroupsResponse, ListSettingsResponse, SettingsFilter,
     SystemSettingResponse, UpdateSettingRequest,
 };
-use crate::models::{RequestContext, UserRole};
+use crate::models::{
+    parse_appointment_buffers, parse_screening_programs, RequestContext, UserRole,
+    APPOINTMENT_BUFFERS_SETTING, SCREENING_PROGRAMS_SETTING,
+};
 
 #[cfg(feature = "rbac")]
 use crate::utils::permissions::require_admin;
@@ -49,6 +55,61 @@ fn error_response(error: &str, message: &str) -> Json<serde_json::Value> {
     }))
 }
 
+/// Validate a new admin IP allowlist value
+///
+/// The value must be an array of CIDR ranges or addresses. A non-empty list
+/// must include the caller's own IP so an admin cannot lock themselves out.
+fn validate_admin_ip_allowlist(
+    value: &serde_json::Value,
+    request_ctx: &RequestContext,
+) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    let entries: Vec<String> = serde_json::from_value(value.clone()).map_err(|_| {
+        (
+            StatusCode::BAD_REQUEST,
+            error_response(
+                "INVALID_VALUE",
+                "Admin IP allowlist must be an array of CIDR ranges or addresses",
+            ),
+        )
+    })?;
+
+    let ranges = parse_ranges(&entries)
+        .map_err(|msg| (StatusCode::BAD_REQUEST, error_response("INVALID_VALUE", &msg)))?;
+
+    if ranges.is_empty() {
+        return Ok(());
+    }
+
+    match parse_client_ip(request_ctx.ip_address.as_deref()) {
+        Some(ip) if is_allowed(&ranges, ip) => Ok(()),
+        _ => Err((
+            StatusCode::BAD_REQUEST,
+            error_response(
+                "LOCKOUT_PREVENTED",
+                "Admin IP allowlist must include the address of the current request",
+            ),
+        )),
+    }
+}
+
+/// Reject screening programs the notification scheduler could not use
+fn validate_screening_programs(
+    value: &serde_json::Value,
+) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    parse_screening_programs(value)
+        .map(|_| ())
+        .map_err(|msg| (StatusCode::BAD_REQUEST, error_response("INVALID_VALUE", &msg)))
+}
+
+/// Reject buffer minutes for unknown appointment types or out of range
+fn validate_appointment_buffers(
+    value: &serde_json::Value,
+) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    parse_appointment_buffers(value)
+        .map(|_| ())
+        .map_err(|msg| (StatusCode::BAD_REQUEST, error_response("INVALID_VALUE", &msg)))
+}
+
 /// List all settings with optional filters
 ///
 /// GET /api/v1/settings
@@ -161,6 +222,16 @@ pub async fn update_setting(
     #[cfg(feature = "rbac")]
     require_admin(&user_role)?;
 
+    if key == ADMIN_IP_ALLOWLIST_SETTING {
+        validate_admin_ip_allowlist(&request.value, &request_ctx)?;
+    }
+    if key == SCREENING_PROGRAMS_SETTING {
+        validate_screening_programs(&request.value)?;
+    }
+    if key == APPOINTMENT_BUFFERS_SETTING {
+        validate_appointment_buffers(&request.value)?;
+    }
+
     let result = state
         .settings_service
         .update_setting(&key, request, user_id, Some(&request_ctx))
@@ -219,6 +290,28 @@ pub async fn bulk_update_settings(
         ));
     }
 
+    for setting in request
+        .settings
+        .iter()
+        .filter(|s| s.key == ADMIN_IP_ALLOWLIST_SETTING)
+    {
+        validate_admin_ip_allowlist(&setting.value, &request_ctx)?;
+    }
+    for setting in request
+        .settings
+        .iter()
+        .filter(|s| s.key == SCREENING_PROGRAMS_SETTING)
+    {
+        validate_screening_programs(&setting.value)?;
+    }
+    for setting in request
+        .settings
+        .iter()
+        .filter(|s| s.key == APPOINTMENT_BUFFERS_SETTING)
+    {
+        validate_appointment_buffers(&setting.value)?;
+    }
+
     let results = state
         .settings_service
         .bulk_update_settings(request, user_id, Some(&request_ctx))
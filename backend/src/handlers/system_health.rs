@@ -9,18 +9,28 @@
  * - GET /api/v1/system/info - System information
  * - GET /api/v1/system/storage - Storage statistics
  * - GET /api/v1/system/backup-status - Backup status
+ * - GET /api/v1/system/alerts - Internal alerts raised by background jobs
+ * - POST /api/v1/system/alerts/:id/acknowledge - Acknowledge an alert
+ * - GET /api/v1/system/telemetry/preview - Payload the next telemetry report would send
+ * - GET /api/v1/system/telemetry/reports - Telemetry reports already sent
  */
 
-use axum::{extract::State, http::StatusCode, Extension, Json};
+use axum::{
+    extract::{Path as PathParam, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
 use std::path::Path;
+use uuid::Uuid;
 
 use crate::{
     handlers::auth::AppState,
     models::{
-        BackupStatusResponse, DetailedHealthResponse, StorageStatsResponse, SystemInfoResponse,
-        UserRole,
+        BackupStatusResponse, DetailedHealthResponse, ListSystemAlertsQuery,
+        ListTelemetryReportsQuery, StorageStatsResponse, SystemAlert, SystemInfoResponse,
+        TelemetryPreviewResponse, TelemetryReport, UserRole,
     },
-    services::SystemHealthService,
+    services::{SystemHealthService, TelemetryService},
 };
 
 #[cfg(feature = "rbac")]
@@ -177,3 +187,152 @@ pub async fn get_backup_status(
 
     Ok(Json(response))
 }
+
+/// List internal alerts raised by background jobs
+///
+/// GET /api/v1/system/alerts?include_acknowledged=false&limit=50
+///
+/// Returns open alerts (most recent first), e.g. notifications or documents
+/// the stuck-state janitor had to mark FAILED.
+///
+/// This endpoint requires ADMIN role.
+pub async fn list_system_alerts(
+    State(state): State<AppState>,
+    Extension(user_role): Extension<UserRole>,
+    Query(query): Query<ListSystemAlertsQuery>,
+) -> Result<Json<Vec<SystemAlert>>, (StatusCode, Json<serde_json::Value>)> {
+    // Check RBAC permission
+    #[cfg(feature = "rbac")]
+    check_permission(&state.enforcer, &user_role, "system", "read").await?;
+
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
+
+    let alerts = SystemAlert::list(&state.pool, query.include_acknowledged, limit)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to list system alerts: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "error": "Failed to list system alerts",
+                    "message": e.to_string()
+                })),
+            )
+        })?;
+
+    Ok(Json(alerts))
+}
+
+/// Acknowledge an alert
+///
+/// POST /api/v1/system/alerts/:id/acknowledge
+///
+/// This endpoint requires ADMIN role.
+pub async fn acknowledge_system_alert(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(user_role): Extension<UserRole>,
+    PathParam(id): PathParam<Uuid>,
+) -> Result<Json<SystemAlert>, (StatusCode, Json<serde_json::Value>)> {
+    // Check RBAC permission
+    #[cfg(feature = "rbac")]
+    check_permission(&state.enforcer, &user_role, "system", "maintenance").await?;
+
+    let alert = SystemAlert::acknowledge(&state.pool, id, user_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to acknowledge system alert {}: {}", id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "error": "Failed to acknowledge system alert",
+                    "message": e.to_string()
+                })),
+            )
+        })?
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({
+                    "error": "System alert not found"
+                })),
+            )
+        })?;
+
+    Ok(Json(alert))
+}
+
+/// Preview anonymous telemetry
+///
+/// GET /api/v1/system/telemetry/preview
+///
+/// Returns the telemetry settings and the exact payload the next report would
+/// send. Nothing is sent by this endpoint.
+///
+/// This endpoint requires ADMIN role.
+pub async fn get_telemetry_preview(
+    State(state): State<AppState>,
+    Extension(user_role): Extension<UserRole>,
+) -> Result<Json<TelemetryPreviewResponse>, (StatusCode, Json<serde_json::Value>)> {
+    // Check RBAC permission
+    #[cfg(feature = "rbac")]
+    check_permission(&state.enforcer, &user_role, "system", "read").await?;
+
+    let service = TelemetryService::new(
+        state.pool.clone(),
+        state.settings_service.clone(),
+        state.environment.clone(),
+    );
+
+    let preview = service.preview().await.map_err(|e| {
+        tracing::error!("Failed to build telemetry preview: {}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "error": "Failed to build telemetry preview",
+                "message": e.to_string()
+            })),
+        )
+    })?;
+
+    Ok(Json(preview))
+}
+
+/// List sent telemetry reports
+///
+/// GET /api/v1/system/telemetry/reports
+///
+/// Returns reports that were posted (most recent first), including the exact
+/// payload and the endpoint's response status.
+///
+/// This endpoint requires ADMIN role.
+pub async fn list_telemetry_reports(
+    State(state): State<AppState>,
+    Extension(user_role): Extension<UserRole>,
+    Query(query): Query<ListTelemetryReportsQuery>,
+) -> Result<Json<Vec<TelemetryReport>>, (StatusCode, Json<serde_json::Value>)> {
+    // Check RBAC permission
+    #[cfg(feature = "rbac")]
+    check_permission(&state.enforcer, &user_role, "system", "read").await?;
+
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+
+    let service = TelemetryService::new(
+        state.pool.clone(),
+        state.settings_service.clone(),
+        state.environment.clone(),
+    );
+
+    let reports = service.list_reports(limit).await.map_err(|e| {
+        tracing::error!("Failed to list telemetry reports: {}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "error": "Failed to list telemetry reports",
+                "message": e.to_string()
+            })),
+        )
+    })?;
+
+    Ok(Json(reports))
+}
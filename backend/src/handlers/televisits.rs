@@ -0,0 +1,329 @@
+/*!
+ * Televisit Handlers
+ *
+ * Video rooms of TELEVISIT appointments. Creating a televisit appointment
+ * provisions its room and emails the patient the join link; staff can
+ * provision again (when the provider failed) and resend the link, and record
+ * when they join and leave. The patient's link needs no sign-in: its signed
+ * token names the appointment and the patient and expires when the room
+ * closes.
+ *
+ * Endpoints:
+ * - GET /api/v1/appointments/:id/televisit - Get the room and attendance
+ * - POST /api/v1/appointments/:id/televisit - Provision the room and email the link
+ * - POST /api/v1/appointments/:id/televisit/join - Record the doctor joining
+ * - POST /api/v1/appointments/:id/televisit/leave - Record the doctor leaving
+ * - POST /api/v1/televisit/join - Patient link: record joining, get the room link
+ * - POST /api/v1/televisit/leave - Patient link: record leaving
+ */
+
+use axum::{
+    extract::{Path, State},
+    Extension, Json,
+};
+use chrono::Utc;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    handlers::{appointments::check_permission, auth::AppState},
+    models::{
+        AuditAction, AuditLog, CreateAuditLog, EntityType, RequestContext, TelevisitAttendance,
+        TelevisitClaims, TelevisitJoinResponse, TelevisitLeaveResponse, TelevisitSession,
+        TelevisitTokenRequest, UserRole,
+    },
+    services::{NotificationService, TelevisitService},
+    utils::{AppError, Result},
+};
+
+/// Provision the room of a televisit appointment and email the patient the
+/// join link
+///
+/// The link is only emailed with a newly provisioned room, or with `resend`.
+/// A failed email is logged; the room is kept.
+pub(crate) async fn provision_televisit(
+    state: &AppState,
+    appointment_id: Uuid,
+    resend: bool,
+    user_id: Uuid,
+) -> Result<TelevisitSession> {
+    let provider = state.video_room_provider.as_ref().ok_or_else(|| {
+        AppError::BadRequest("Televisits are not configured (VIDEO_PROVIDER)".to_string())
+    })?;
+
+    let service = TelevisitService::new(state.pool.clone());
+    let (mut session, created) = service
+        .provision(appointment_id, provider.as_ref(), user_id)
+        .await?;
+
+    if created || resend {
+        match (&state.email_service, &state.encryption_key) {
+            (Some(email_service), Some(encryption_key)) => {
+                let notification_service =
+                    NotificationService::new(state.pool.clone(), email_service.clone());
+                match service
+                    .send_invitation(&session, &notification_service, encryption_key, user_id)
+                    .await
+                {
+                    Ok(Some(notification_id)) => {
+                        session.invitation_notification_id = Some(notification_id);
+                    }
+                    Ok(None) => tracing::info!(
+                        "Televisit invitation not sent for appointment {} - patient has no email or email notifications disabled",
+                        appointment_id
+                    ),
+                    Err(e) => tracing::warn!(
+                        "Failed to send televisit invitation for appointment {}: {}",
+                        appointment_id,
+                        e
+                    ),
+                }
+            }
+            _ => tracing::warn!(
+                "Televisit invitation not sent for appointment {} - email not configured",
+                appointment_id
+            ),
+        }
+    }
+
+    Ok(session)
+}
+
+async fn audit_televisit(
+    state: &AppState,
+    user_id: Option<Uuid>,
+    request_ctx: &RequestContext,
+    action: AuditAction,
+    session_id: Uuid,
+    changes: serde_json::Value,
+) {
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id,
+            action,
+            entity_type: EntityType::TelevisitSession,
+            entity_id: Some(session_id.to_string()),
+            changes: Some(changes),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+}
+
+fn verify_token(state: &AppState, req: &TelevisitTokenRequest) -> Result<TelevisitClaims> {
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let encryption_key = state
+        .encryption_key
+        .as_ref()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+
+    NotificationService::verify_televisit_token(encryption_key, &req.token, Utc::now())
+        .ok_or_else(|| AppError::NotFound("This link is invalid or has expired".to_string()))
+}
+
+/// Get the room and attendance of a televisit
+///
+/// GET /api/v1/appointments/:id/televisit
+pub async fn get_televisit(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(user_role): Extension<UserRole>,
+    Path(appointment_id): Path<Uuid>,
+) -> Result<Json<TelevisitSession>> {
+    check_permission(&state, &user_role, "read").await?;
+
+    let session = TelevisitService::new(state.pool.clone())
+        .get_session(appointment_id, user_id)
+        .await?;
+
+    Ok(Json(session))
+}
+
+/// Provision the room of a televisit and email the patient the join link
+///
+/// POST /api/v1/appointments/:id/televisit
+///
+/// Creates the room if the appointment has none (for example when the video
+/// provider failed at creation) and emails the link; with a room already
+/// there, emails the link again, for example after a reschedule.
+pub async fn create_televisit(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(user_role): Extension<UserRole>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(appointment_id): Path<Uuid>,
+) -> Result<Json<TelevisitSession>> {
+    check_permission(&state, &user_role, "update").await?;
+
+    let session = provision_televisit(&state, appointment_id, true, user_id).await?;
+
+    audit_televisit(
+        &state,
+        Some(user_id),
+        &request_ctx,
+        AuditAction::Create,
+        session.id,
+        serde_json::json!({
+            "appointment_id": appointment_id,
+            "video_provider": session.video_provider,
+            "invitation_notification_id": session.invitation_notification_id,
+        }),
+    )
+    .await;
+
+    Ok(Json(session))
+}
+
+async fn record_provider_attendance(
+    state: &AppState,
+    user_id: Uuid,
+    user_role: &UserRole,
+    request_ctx: &RequestContext,
+    appointment_id: Uuid,
+    attendance: TelevisitAttendance,
+) -> Result<Json<TelevisitSession>> {
+    check_permission(state, user_role, "update").await?;
+
+    let session = TelevisitService::new(state.pool.clone())
+        .record_provider_attendance(appointment_id, attendance, user_id)
+        .await?;
+
+    audit_televisit(
+        state,
+        Some(user_id),
+        request_ctx,
+        AuditAction::Update,
+        session.id,
+        serde_json::json!({
+            "appointment_id": appointment_id,
+            "participant": "provider",
+            "attendance": attendance.as_str(),
+        }),
+    )
+    .await;
+
+    Ok(Json(session))
+}
+
+/// Record the doctor joining a televisit
+///
+/// POST /api/v1/appointments/:id/televisit/join
+///
+/// Keeps the first join. Answers 409 for a cancelled, missed or completed
+/// appointment.
+pub async fn join_televisit(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(user_role): Extension<UserRole>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(appointment_id): Path<Uuid>,
+) -> Result<Json<TelevisitSession>> {
+    record_provider_attendance(
+        &state,
+        user_id,
+        &user_role,
+        &request_ctx,
+        appointment_id,
+        TelevisitAttendance::Join,
+    )
+    .await
+}
+
+/// Record the doctor leaving a televisit
+///
+/// POST /api/v1/appointments/:id/televisit/leave
+///
+/// Keeps the last leave.
+pub async fn leave_televisit(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(user_role): Extension<UserRole>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(appointment_id): Path<Uuid>,
+) -> Result<Json<TelevisitSession>> {
+    record_provider_attendance(
+        &state,
+        user_id,
+        &user_role,
+        &request_ctx,
+        appointment_id,
+        TelevisitAttendance::Leave,
+    )
+    .await
+}
+
+/// Join a televisit from the invitation link
+///
+/// POST /api/v1/televisit/join
+///
+/// Records the patient joining and answers the room link. The room opens
+/// shortly before the appointment; earlier, or once cancelled or ended, the
+/// answer is 409.
+pub async fn patient_join_televisit(
+    State(state): State<AppState>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Json(req): Json<TelevisitTokenRequest>,
+) -> Result<Json<TelevisitJoinResponse>> {
+    let claims = verify_token(&state, &req)?;
+
+    let (session_id, response) = TelevisitService::new(state.pool.clone())
+        .patient_join(&claims)
+        .await?;
+
+    // No staff user: the patient is in the payload; the room link stays out
+    audit_televisit(
+        &state,
+        None,
+        &request_ctx,
+        AuditAction::Update,
+        session_id,
+        serde_json::json!({
+            "source": "televisit_link",
+            "appointment_id": claims.appointment_id,
+            "patient_id": claims.patient_id,
+            "participant": "patient",
+            "attendance": TelevisitAttendance::Join.as_str(),
+        }),
+    )
+    .await;
+
+    Ok(Json(response))
+}
+
+/// Leave a televisit from the invitation link
+///
+/// POST /api/v1/televisit/leave
+pub async fn patient_leave_televisit(
+    State(state): State<AppState>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Json(req): Json<TelevisitTokenRequest>,
+) -> Result<Json<TelevisitLeaveResponse>> {
+    let claims = verify_token(&state, &req)?;
+
+    let (session_id, response) = TelevisitService::new(state.pool.clone())
+        .patient_leave(&claims)
+        .await?;
+
+    audit_televisit(
+        &state,
+        None,
+        &request_ctx,
+        AuditAction::Update,
+        session_id,
+        serde_json::json!({
+            "source": "televisit_link",
+            "appointment_id": claims.appointment_id,
+            "patient_id": claims.patient_id,
+            "participant": "patient",
+            "attendance": TelevisitAttendance::Leave.as_str(),
+        }),
+    )
+    .await;
+
+    Ok(Json(response))
+}
@@ -0,0 +1,162 @@
+/*!
+ * Recycle Bin Handlers
+ *
+ * Deleted patients, draft visits and prescriptions, and their restore. A
+ * user sees and restores the record types they may delete; purging is done
+ * by the RECYCLE_BIN retention rule (ADMIN only).
+ *
+ * Endpoints:
+ * - GET /api/v1/trash - List the recycle bin
+ * - POST /api/v1/trash/:entity_type/:id/restore - Restore a record
+ *   (`entity_type` is `patients`, `visits` or `prescriptions`)
+ */
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use uuid::Uuid;
+
+use crate::{
+    handlers::auth::AppState,
+    models::{
+        AuditAction, AuditLog, AuthUser, CreateAuditLog, EntityType, ListTrashQuery,
+        RequestContext, TrashEntityType, TrashListResponse, UserRole,
+    },
+    services::TrashService,
+    utils::{AppError, Result},
+};
+
+#[cfg(feature = "rbac")]
+use tracing::warn;
+
+/// Whether the role may delete, and so see and restore, records of a type
+#[cfg(feature = "rbac")]
+async fn can_manage(state: &AppState, user_role: &UserRole, entity_type: TrashEntityType) -> bool {
+    state
+        .enforcer
+        .enforce(user_role, entity_type.resource(), "delete")
+        .await
+        .unwrap_or_else(|e| {
+            warn!("RBAC enforcement error: {}", e);
+            false
+        })
+}
+
+/// Fallback for non-RBAC builds - only checks role
+#[cfg(not(feature = "rbac"))]
+async fn can_manage(_state: &AppState, user_role: &UserRole, entity_type: TrashEntityType) -> bool {
+    match entity_type {
+        TrashEntityType::Visit => matches!(user_role, UserRole::Admin | UserRole::Doctor),
+        _ => matches!(user_role, UserRole::Admin),
+    }
+}
+
+fn trash_service(state: &AppState) -> Result<TrashService> {
+    let encryption_key = state
+        .encryption_key
+        .clone()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+    Ok(TrashService::new(state.pool.clone(), encryption_key))
+}
+
+/// List the recycle bin, most recently deleted first
+///
+/// GET /api/v1/trash?entity_type=VISIT&limit=50&offset=0
+///
+/// Without `entity_type`, lists every type the user may delete.
+///
+/// **RBAC**: Requires 'delete' permission on the resource of each type
+/// listed ('patients', 'visits', 'prescriptions')
+pub async fn list_trash(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Query(query): Query<ListTrashQuery>,
+) -> Result<Json<TrashListResponse>> {
+    let candidates = match query.entity_type {
+        Some(entity_type) => vec![entity_type],
+        None => TrashEntityType::ALL.to_vec(),
+    };
+
+    let mut entity_types = Vec::new();
+    for entity_type in candidates {
+        if can_manage(&state, &auth_user.role, entity_type).await {
+            entity_types.push(entity_type);
+        }
+    }
+    if entity_types.is_empty() {
+        return Err(AppError::Forbidden(
+            "User does not have permission to view the recycle bin".to_string(),
+        ));
+    }
+
+    let list = trash_service(&state)?
+        .list(&entity_types, &query, auth_user.user_id)
+        .await?;
+
+    Ok(Json(list))
+}
+
+/// Restore a record from the recycle bin
+///
+/// POST /api/v1/trash/:entity_type/:id/restore
+///
+/// Visits and prescriptions of a patient still in the bin are refused with
+/// 409 until the patient is restored.
+///
+/// **RBAC**: Requires 'delete' permission on the record's resource
+pub async fn restore_trash_item(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path((resource, id)): Path<(String, Uuid)>,
+) -> Result<StatusCode> {
+    let entity_type = TrashEntityType::from_resource(&resource).ok_or_else(|| {
+        AppError::BadRequest(format!(
+            "Unknown record type '{}' (expected patients, visits or prescriptions)",
+            resource
+        ))
+    })?;
+
+    if !can_manage(&state, &auth_user.role, entity_type).await {
+        return Err(AppError::Forbidden(format!(
+            "User does not have permission to restore {}",
+            resource
+        )));
+    }
+
+    let patient_id = trash_service(&state)?
+        .restore(entity_type, id, auth_user.user_id)
+        .await?;
+
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(auth_user.user_id),
+            action: AuditAction::Update,
+            entity_type: match entity_type {
+                TrashEntityType::Patient => EntityType::Patient,
+                TrashEntityType::Visit => EntityType::Visit,
+                TrashEntityType::Prescription => EntityType::Prescription,
+            },
+            entity_id: Some(id.to_string()),
+            changes: Some(serde_json::json!({
+                "action": "restore",
+                "patient_id": patient_id,
+            })),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+
+    tracing::info!(
+        "{} {} restored from the recycle bin",
+        entity_type.as_str(),
+        id
+    );
+
+    Ok(StatusCode::NO_CONTENT)
+}
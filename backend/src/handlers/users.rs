@@ -12,8 +12,13 @@ use uuid::Uuid;
 
 use crate::handlers::auth::AppState;
 use crate::models::user::{User, UserRole};
+use crate::models::access_delegation::SetLocumRequest;
 use crate::models::{AuditAction, AuditLog, CreateAuditLog, EntityType, RequestContext};
+use crate::services::{
+    DelegationService, PasswordPolicy, PasswordPolicyService, TrustedDeviceService,
+};
 use crate::utils::password::{validate_password, PasswordHasherUtil};
+use crate::utils::AppError;
 
 #[cfg(feature = "rbac")]
 use crate::utils::permissions::{require_admin, require_user_access};
@@ -628,47 +633,30 @@ pub async fn reset_password(
     #[cfg(feature = "rbac")]
     require_admin(&user_role)?;
 
-    // Validate password complexity
-    validate_password(&req.new_password).map_err(|e| {
-        (
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({
-                "error": "INVALID_PASSWORD",
-                "message": e
-            })),
-        )
-    })?;
-
-    // Hash password
-    let password_hash = PasswordHasherUtil::hash_password(&req.new_password).map_err(|e| {
-        tracing::error!("Password hashing error: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({
-                "error": "INTERNAL_ERROR",
-                "message": "Failed to process password"
-            })),
-        )
-    })?;
-
-    // Reset failed login attempts and update password
-    sqlx::query(
-        "UPDATE users SET password_hash = $1, failed_login_attempts = 0, locked_until = NULL, updated_at = NOW() WHERE id = $2"
-    )
-    .bind(&password_hash)
-    .bind(user_id)
-    .execute(pool)
-    .await
-    .map_err(|e| {
-        tracing::error!("Database error resetting password: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({
-                "error": "INTERNAL_ERROR",
-                "message": "Failed to reset password"
-            })),
-        )
-    })?;
+    // Validate complexity and reuse history, then store the new password
+    let policy = PasswordPolicy::load(&state.settings_service).await;
+    PasswordPolicyService::new(pool.clone())
+        .set_password(user_id, &req.new_password, &policy)
+        .await
+        .map_err(|e| match e {
+            AppError::Validation(message) => (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({
+                    "error": "INVALID_PASSWORD",
+                    "message": message
+                })),
+            ),
+            e => {
+                tracing::error!("Database error resetting password: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(serde_json::json!({
+                        "error": "INTERNAL_ERROR",
+                        "message": "Failed to reset password"
+                    })),
+                )
+            }
+        })?;
 
     // Create audit log for password reset (don't log the actual password!)
     let _ = AuditLog::create(
@@ -743,6 +731,15 @@ pub async fn reset_mfa(
         )
     })?;
 
+    // Devices trusted under the old MFA enrollment must not skip the new one
+    let trusted_devices_revoked = TrustedDeviceService::new(pool.clone())
+        .revoke_all(user_id)
+        .await
+        .unwrap_or_else(|e| {
+            tracing::error!("Failed to revoke trusted devices for user {}: {}", user_id, e);
+            0
+        });
+
     // Create audit log for MFA reset
     let _ = AuditLog::create(
         pool,
@@ -754,6 +751,7 @@ pub async fn reset_mfa(
             changes: Some(serde_json::json!({
                 "action": "reset_mfa",
                 "mfa_enabled": false,
+                "trusted_devices_revoked": trusted_devices_revoked,
             })),
             ip_address: request_ctx.ip_address.clone(),
             user_agent: request_ctx.user_agent.clone(),
@@ -766,3 +764,65 @@ pub async fn reset_mfa(
 
     Ok((StatusCode::OK, Json(UserResponse::from(user))))
 }
+
+/// Mark or unmark a user as a locum account (ADMIN only)
+///
+/// Locum accounts only see patients covered by an active access delegation
+/// and are turned away from clinical routes outside the delegation window.
+pub async fn set_locum(
+    State(state): State<AppState>,
+    Path(user_id): Path<Uuid>,
+    Extension(current_user_id): Extension<Uuid>,
+    Extension(user_role): Extension<UserRole>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Json(req): Json<SetLocumRequest>,
+) -> Result<StatusCode, (StatusCode, Json<serde_json::Value>)> {
+    let pool = &state.pool;
+    #[cfg(feature = "rbac")]
+    require_admin(&user_role)?;
+
+    let updated = DelegationService::new(pool.clone())
+        .set_locum(user_id, req.is_locum)
+        .await
+        .map_err(|e| {
+            tracing::error!("Database error updating locum flag for user {}: {}", user_id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "error": "INTERNAL_ERROR",
+                    "message": "Failed to update locum flag"
+                })),
+            )
+        })?;
+
+    if !updated {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "error": "USER_NOT_FOUND",
+                "message": "User not found"
+            })),
+        ));
+    }
+
+    // Create audit log for locum flag change
+    let _ = AuditLog::create(
+        pool,
+        CreateAuditLog {
+            user_id: Some(current_user_id),
+            action: AuditAction::Update,
+            entity_type: EntityType::User,
+            entity_id: Some(user_id.to_string()),
+            changes: Some(serde_json::json!({
+                "action": "set_locum",
+                "is_locum": req.is_locum,
+            })),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,143 @@
+/*!
+ * Visit Addendum Handlers
+ *
+ * Signed addenda and corrections appended to signed or locked visits. The
+ * visit note itself is never changed.
+ *
+ * Endpoints:
+ * - GET /api/v1/visits/:id/addenda - Addenda of a visit
+ * - POST /api/v1/visits/:id/addenda - Sign and append an addendum
+ */
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    handlers::auth::AppState,
+    models::{
+        AuditAction, AuditLog, AuthUser, CreateAuditLog, CreateVisitAddendumRequest, EntityType,
+        RequestContext, UserRole, VisitAddendumResponse,
+    },
+    services::VisitAddendumService,
+    utils::{AppError, Result},
+};
+
+#[cfg(feature = "rbac")]
+use tracing::warn;
+
+/// Check if user has permission to perform action on visits resource
+#[cfg(feature = "rbac")]
+async fn check_permission(state: &AppState, user_role: &UserRole, action: &str) -> Result<()> {
+    let has_permission = state
+        .enforcer
+        .enforce(user_role, "visits", action)
+        .await
+        .map_err(|e| {
+            warn!("RBAC enforcement error: {}", e);
+            AppError::Internal("Failed to check permissions".to_string())
+        })?;
+
+    if !has_permission {
+        return Err(AppError::Forbidden(format!(
+            "User does not have permission to {} visits",
+            action
+        )));
+    }
+
+    Ok(())
+}
+
+/// Fallback for non-RBAC builds - only checks role
+#[cfg(not(feature = "rbac"))]
+async fn check_permission(_state: &AppState, user_role: &UserRole, action: &str) -> Result<()> {
+    let allowed = match action {
+        "amend" => matches!(user_role, UserRole::Admin | UserRole::Doctor),
+        _ => matches!(
+            user_role,
+            UserRole::Admin | UserRole::Doctor | UserRole::Nurse
+        ),
+    };
+
+    if !allowed {
+        return Err(AppError::Forbidden("Insufficient permissions".to_string()));
+    }
+    Ok(())
+}
+
+fn addendum_service(state: &AppState) -> Result<VisitAddendumService> {
+    let encryption_key = state
+        .encryption_key
+        .clone()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+    Ok(VisitAddendumService::new(state.pool.clone(), encryption_key))
+}
+
+/// Addenda of a visit, oldest first
+///
+/// GET /api/v1/visits/:id/addenda
+///
+/// **RBAC**: Requires 'read' permission on 'visits' resource
+pub async fn list_visit_addenda(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(visit_id): Path<Uuid>,
+) -> Result<Json<Vec<VisitAddendumResponse>>> {
+    check_permission(&state, &auth_user.role, "read").await?;
+
+    let addenda = addendum_service(&state)?
+        .list_addenda(visit_id, auth_user.user_id)
+        .await?;
+
+    Ok(Json(addenda))
+}
+
+/// Sign and append an addendum to a signed or locked visit
+///
+/// POST /api/v1/visits/:id/addenda
+///
+/// **RBAC**: Requires 'amend' permission on 'visits' resource
+pub async fn create_visit_addendum(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(visit_id): Path<Uuid>,
+    Json(request): Json<CreateVisitAddendumRequest>,
+) -> Result<(StatusCode, Json<VisitAddendumResponse>)> {
+    check_permission(&state, &auth_user.role, "amend").await?;
+
+    request
+        .validate()
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+
+    let addendum = addendum_service(&state)?
+        .create_addendum(visit_id, &request, auth_user.user_id)
+        .await?;
+
+    // The payload never contains the addendum text
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(auth_user.user_id),
+            action: AuditAction::Create,
+            entity_type: EntityType::VisitAddendum,
+            entity_id: Some(addendum.id.to_string()),
+            changes: Some(serde_json::json!({
+                "visit_id": visit_id,
+                "patient_id": addendum.patient_id,
+                "addendum_type": addendum.addendum_type,
+                "signature_hash": addendum.signature_hash,
+            })),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+
+    Ok((StatusCode::CREATED, Json(addendum)))
+}
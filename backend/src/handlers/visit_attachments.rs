@@ -0,0 +1,348 @@
+/*!
+ * Visit Attachment Handlers
+ *
+ * Clinical photos, audio memos and files attached to a visit, encrypted at
+ * rest. Attachments change only while the visit is a draft. Every download
+ * is recorded in the audit log.
+ *
+ * Endpoints:
+ * - GET /api/v1/visits/:id/attachments - List a visit's attachments
+ * - POST /api/v1/visits/:id/attachments - Upload an attachment (multipart)
+ * - DELETE /api/v1/visits/:id/attachments/:attachment_id - Delete an attachment
+ * - GET /api/v1/visits/:id/attachments/:attachment_id/download - Download the file
+ * - POST /api/v1/visits/:id/attachments/:attachment_id/transcription - Transcribe again
+ */
+
+use axum::{
+    extract::{Multipart, Path, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::IntoResponse,
+    Extension, Json,
+};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    handlers::auth::AppState,
+    models::{
+        uploaded_file::MAX_FILE_SIZE, AuditAction, AuditLog, AuthUser, CreateAuditLog,
+        CreateVisitAttachmentRequest, EntityType, RequestContext, UserRole,
+        VisitAttachmentResponse,
+    },
+    services::VisitAttachmentService,
+    utils::{AppError, Result},
+};
+
+#[cfg(feature = "rbac")]
+use tracing::warn;
+
+/// Check if user has permission to perform action on attachments resource
+#[cfg(feature = "rbac")]
+async fn check_permission(state: &AppState, user_role: &UserRole, action: &str) -> Result<()> {
+    let has_permission = state
+        .enforcer
+        .enforce(user_role, "attachments", action)
+        .await
+        .map_err(|e| {
+            warn!("RBAC enforcement error: {}", e);
+            AppError::Internal("Failed to check permissions".to_string())
+        })?;
+
+    if !has_permission {
+        return Err(AppError::Forbidden(format!(
+            "User does not have permission to {} attachments",
+            action
+        )));
+    }
+
+    Ok(())
+}
+
+/// Fallback for non-RBAC builds - only checks role
+#[cfg(not(feature = "rbac"))]
+async fn check_permission(_state: &AppState, user_role: &UserRole, action: &str) -> Result<()> {
+    let allowed = match action {
+        "read" | "create" => matches!(
+            user_role,
+            UserRole::Admin | UserRole::Doctor | UserRole::Nurse
+        ),
+        _ => matches!(user_role, UserRole::Admin | UserRole::Doctor),
+    };
+
+    if !allowed {
+        return Err(AppError::Forbidden("Insufficient permissions".to_string()));
+    }
+    Ok(())
+}
+
+fn attachment_service(state: &AppState) -> Result<VisitAttachmentService> {
+    let encryption_key = state
+        .encryption_key
+        .clone()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+    Ok(VisitAttachmentService::new(
+        state.pool.clone(),
+        encryption_key,
+    ))
+}
+
+async fn audit_attachment(
+    state: &AppState,
+    auth_user: &AuthUser,
+    request_ctx: &RequestContext,
+    action: AuditAction,
+    attachment_id: Uuid,
+    changes: serde_json::Value,
+) {
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(auth_user.user_id),
+            action,
+            entity_type: EntityType::VisitAttachment,
+            entity_id: Some(attachment_id.to_string()),
+            changes: Some(changes),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+}
+
+/// List a visit's attachments, oldest first
+///
+/// GET /api/v1/visits/:id/attachments
+///
+/// **RBAC**: Requires 'read' permission on 'attachments' resource
+pub async fn list_visit_attachments(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(visit_id): Path<Uuid>,
+) -> Result<Json<Vec<VisitAttachmentResponse>>> {
+    check_permission(&state, &auth_user.role, "read").await?;
+
+    let attachments = attachment_service(&state)?
+        .list_attachments(visit_id, auth_user.user_id)
+        .await?;
+
+    Ok(Json(attachments))
+}
+
+/// Upload an attachment to a draft visit
+///
+/// POST /api/v1/visits/:id/attachments
+///
+/// Multipart form: `file` (JPEG, PNG or WebP photo; MP3, WAV, Ogg, M4A or
+/// WebM audio; or PDF, at most 10 MB), and optionally `caption` and
+/// `body_site`.
+///
+/// **RBAC**: Requires 'create' permission on 'attachments' resource
+pub async fn create_visit_attachment(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(visit_id): Path<Uuid>,
+    mut multipart: Multipart,
+) -> Result<(StatusCode, Json<VisitAttachmentResponse>)> {
+    check_permission(&state, &auth_user.role, "create").await?;
+
+    let mut file: Option<(Vec<u8>, String)> = None;
+    let mut req = CreateVisitAttachmentRequest::default();
+
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|_| AppError::BadRequest("Failed to parse multipart form".to_string()))?
+    {
+        let name = field.name().unwrap_or("").to_string();
+        if name == "file" {
+            let filename = field.file_name().unwrap_or("attachment").to_string();
+            let content = field
+                .bytes()
+                .await
+                .map_err(|_| AppError::BadRequest("Failed to read file".to_string()))?;
+            if content.len() > MAX_FILE_SIZE {
+                return Err(AppError::Validation(format!(
+                    "File exceeds maximum size of {} MB",
+                    MAX_FILE_SIZE / 1024 / 1024
+                )));
+            }
+            file = Some((content.to_vec(), filename));
+            continue;
+        }
+
+        let value = field
+            .text()
+            .await
+            .map_err(|_| AppError::BadRequest(format!("Failed to read field '{}'", name)))?;
+        let value = value.trim().to_string();
+        if value.is_empty() {
+            continue;
+        }
+
+        match name.as_str() {
+            "caption" => req.caption = Some(value),
+            "body_site" => req.body_site = Some(value),
+            _ => {}
+        }
+    }
+
+    let (content, filename) =
+        file.ok_or_else(|| AppError::BadRequest("No file provided".to_string()))?;
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let attachment = attachment_service(&state)?
+        .create_attachment(visit_id, &content, &filename, &req, auth_user.user_id)
+        .await?;
+
+    // The payload never contains the caption
+    audit_attachment(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Create,
+        attachment.id,
+        serde_json::json!({
+            "visit_id": visit_id,
+            "patient_id": attachment.patient_id,
+            "kind": attachment.kind,
+            "mime_type": attachment.mime_type,
+            "file_size_bytes": attachment.file_size_bytes,
+        }),
+    )
+    .await;
+
+    Ok((StatusCode::CREATED, Json(attachment)))
+}
+
+/// Download an attachment
+///
+/// GET /api/v1/visits/:id/attachments/:attachment_id/download
+///
+/// Photos and audio are served inline so they can be shown and played in
+/// the visit; every download is recorded in the audit log.
+///
+/// **RBAC**: Requires 'read' permission on 'attachments' resource
+pub async fn download_visit_attachment(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path((visit_id, attachment_id)): Path<(Uuid, Uuid)>,
+) -> Result<impl IntoResponse> {
+    check_permission(&state, &auth_user.role, "read").await?;
+
+    let (attachment, content) = attachment_service(&state)?
+        .download_attachment(visit_id, attachment_id, auth_user.user_id)
+        .await?;
+
+    audit_attachment(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Read,
+        attachment_id,
+        serde_json::json!({
+            "type": "visit_attachment_download",
+            "visit_id": visit_id,
+            "patient_id": attachment.patient_id,
+        }),
+    )
+    .await;
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_str(&attachment.mime_type)
+            .unwrap_or(HeaderValue::from_static("application/octet-stream")),
+    );
+    let disposition_type = if attachment.kind == "FILE" {
+        "attachment"
+    } else {
+        "inline"
+    };
+    let disposition = format!(
+        "{}; filename=\"{}\"",
+        disposition_type,
+        attachment.original_filename.replace('"', "\\\"")
+    );
+    headers.insert(
+        header::CONTENT_DISPOSITION,
+        HeaderValue::from_str(&disposition).unwrap_or(HeaderValue::from_static("attachment")),
+    );
+    // Patient data: never cached
+    headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    headers.insert(
+        header::X_CONTENT_TYPE_OPTIONS,
+        HeaderValue::from_static("nosniff"),
+    );
+
+    Ok((StatusCode::OK, headers, content))
+}
+
+/// Delete an attachment of a draft visit
+///
+/// DELETE /api/v1/visits/:id/attachments/:attachment_id
+///
+/// The attachment is marked deleted; the encrypted file is kept with the
+/// medical record.
+///
+/// **RBAC**: Requires 'delete' permission on 'attachments' resource
+pub async fn delete_visit_attachment(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path((visit_id, attachment_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode> {
+    check_permission(&state, &auth_user.role, "delete").await?;
+
+    attachment_service(&state)?
+        .delete_attachment(visit_id, attachment_id, auth_user.user_id)
+        .await?;
+
+    audit_attachment(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Delete,
+        attachment_id,
+        serde_json::json!({ "visit_id": visit_id }),
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Queue an audio memo for transcription again
+///
+/// POST /api/v1/visits/:id/attachments/:attachment_id/transcription
+///
+/// **RBAC**: Requires 'update' permission on 'attachments' resource
+pub async fn requeue_visit_attachment_transcription(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path((visit_id, attachment_id)): Path<(Uuid, Uuid)>,
+) -> Result<(StatusCode, Json<VisitAttachmentResponse>)> {
+    check_permission(&state, &auth_user.role, "update").await?;
+
+    let attachment = attachment_service(&state)?
+        .requeue_transcription(visit_id, attachment_id, auth_user.user_id)
+        .await?;
+
+    audit_attachment(
+        &state,
+        &auth_user,
+        &request_ctx,
+        AuditAction::Update,
+        attachment_id,
+        serde_json::json!({
+            "type": "visit_attachment_transcription_requeue",
+            "visit_id": visit_id,
+        }),
+    )
+    .await;
+
+    Ok((StatusCode::ACCEPTED, Json(attachment)))
+}
@@ -0,0 +1,288 @@
+/*!
+ * Visit Co-Signature Handlers
+ *
+ * Visits signed by a user under supervision wait in PENDING_COSIGN until
+ * their supervisor countersigns them or returns them to draft.
+ *
+ * Endpoints:
+ * - GET /api/v1/visits/cosign-queue - Visits awaiting the caller's co-signature
+ * - POST /api/v1/visits/:id/cosign - Countersign (PENDING_COSIGN → SIGNED)
+ * - POST /api/v1/visits/:id/cosign/return - Return to the author (PENDING_COSIGN → DRAFT)
+ * - GET /api/v1/visits/cosign-requirements - Users whose visits need co-signature
+ * - PUT /api/v1/visits/cosign-requirements/:user_id - Set a user's supervisor
+ * - DELETE /api/v1/visits/cosign-requirements/:user_id - Remove a requirement
+ */
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    handlers::auth::AppState,
+    models::{
+        AuditAction, AuditLog, AuthUser, CosignQueueItem, CosignQueueQuery, CosignRequirement,
+        CreateAuditLog, EntityType, RequestContext, ReturnVisitRequest,
+        SetCosignRequirementRequest, UserRole, VisitResponse,
+    },
+    services::{VisitCosignService, VisitService},
+    utils::{AppError, Result},
+};
+
+#[cfg(feature = "rbac")]
+use tracing::warn;
+
+/// Check if user has permission to perform action on visits resource
+#[cfg(feature = "rbac")]
+async fn check_permission(state: &AppState, user_role: &UserRole, action: &str) -> Result<()> {
+    let has_permission = state
+        .enforcer
+        .enforce(user_role, "visits", action)
+        .await
+        .map_err(|e| {
+            warn!("RBAC enforcement error: {}", e);
+            AppError::Internal("Failed to check permissions".to_string())
+        })?;
+
+    if !has_permission {
+        return Err(AppError::Forbidden(format!(
+            "User does not have permission to {} visits",
+            action
+        )));
+    }
+
+    Ok(())
+}
+
+/// Fallback for non-RBAC builds - only checks role
+#[cfg(not(feature = "rbac"))]
+async fn check_permission(_state: &AppState, user_role: &UserRole, action: &str) -> Result<()> {
+    let allowed = match action {
+        "manage_cosign" => matches!(user_role, UserRole::Admin),
+        _ => matches!(user_role, UserRole::Admin | UserRole::Doctor),
+    };
+
+    if !allowed {
+        return Err(AppError::Forbidden("Insufficient permissions".to_string()));
+    }
+    Ok(())
+}
+
+fn cosign_service(state: &AppState) -> Result<VisitCosignService> {
+    let encryption_key = state
+        .encryption_key
+        .clone()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+    Ok(VisitCosignService::new(state.pool.clone(), encryption_key))
+}
+
+fn visit_service(state: &AppState) -> Result<VisitService> {
+    let encryption_key = state
+        .encryption_key
+        .clone()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+    Ok(VisitService::new(state.pool.clone(), encryption_key))
+}
+
+/// Map a co-signature failure from the visit service
+fn cosign_error(id: Uuid, e: anyhow::Error) -> AppError {
+    tracing::error!("Failed to co-sign visit {}: {}", id, e);
+    let message = e.to_string();
+    if message.contains("Visit not found") {
+        AppError::NotFound(format!("Visit {} not found", id))
+    } else if message.contains("Cannot co-sign") {
+        AppError::BadRequest(message)
+    } else {
+        AppError::Internal(format!("Failed to co-sign visit: {}", message))
+    }
+}
+
+/// Audit a change to a user's co-signature requirement
+async fn audit_requirement(
+    state: &AppState,
+    auth_user: &AuthUser,
+    request_ctx: &RequestContext,
+    user_id: Uuid,
+    changes: serde_json::Value,
+) {
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(auth_user.user_id),
+            action: AuditAction::Update,
+            entity_type: EntityType::User,
+            entity_id: Some(user_id.to_string()),
+            changes: Some(changes),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+}
+
+/// Visits awaiting co-signature, oldest first
+///
+/// GET /api/v1/visits/cosign-queue
+///
+/// Doctors see the visits sent to them; admins see every supervisor's
+/// queue, or one with `supervisor_id`.
+///
+/// **RBAC**: Requires 'cosign' permission on 'visits' resource
+pub async fn get_cosign_queue(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Query(query): Query<CosignQueueQuery>,
+) -> Result<Json<Vec<CosignQueueItem>>> {
+    check_permission(&state, &auth_user.role, "cosign").await?;
+
+    let supervisor_id = match auth_user.role {
+        UserRole::Admin => query.supervisor_id,
+        _ => Some(auth_user.user_id),
+    };
+
+    let queue = cosign_service(&state)?
+        .queue(supervisor_id, auth_user.user_id)
+        .await?;
+
+    Ok(Json(queue))
+}
+
+/// Countersign a visit awaiting co-signature
+///
+/// POST /api/v1/visits/:id/cosign
+///
+/// **RBAC**: Requires 'cosign' permission on 'visits' resource
+/// **Business Rule**: Only the visit's supervisor (or an admin) can countersign
+pub async fn cosign_visit(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<VisitResponse>> {
+    check_permission(&state, &auth_user.role, "cosign").await?;
+
+    let visit = visit_service(&state)?
+        .cosign_visit(
+            id,
+            auth_user.user_id,
+            auth_user.role == UserRole::Admin,
+            Some(&request_ctx),
+        )
+        .await
+        .map_err(|e| cosign_error(id, e))?;
+
+    Ok(Json(visit))
+}
+
+/// Return a visit awaiting co-signature to its author
+///
+/// POST /api/v1/visits/:id/cosign/return
+///
+/// **RBAC**: Requires 'cosign' permission on 'visits' resource
+/// **Business Rule**: Only the visit's supervisor (or an admin) can return it
+pub async fn return_visit_for_revision(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(id): Path<Uuid>,
+    Json(request): Json<ReturnVisitRequest>,
+) -> Result<Json<VisitResponse>> {
+    check_permission(&state, &auth_user.role, "cosign").await?;
+
+    request
+        .validate()
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+
+    let visit = visit_service(&state)?
+        .return_visit_for_revision(
+            id,
+            auth_user.user_id,
+            auth_user.role == UserRole::Admin,
+            request.note.trim(),
+            Some(&request_ctx),
+        )
+        .await
+        .map_err(|e| cosign_error(id, e))?;
+
+    Ok(Json(visit))
+}
+
+/// Users whose visits need co-signature
+///
+/// GET /api/v1/visits/cosign-requirements
+///
+/// **RBAC**: Requires 'manage_cosign' permission on 'visits' resource
+pub async fn list_cosign_requirements(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+) -> Result<Json<Vec<CosignRequirement>>> {
+    check_permission(&state, &auth_user.role, "manage_cosign").await?;
+
+    let requirements = cosign_service(&state)?.list_requirements().await?;
+
+    Ok(Json(requirements))
+}
+
+/// Require co-signature of a user's visits by a supervisor
+///
+/// PUT /api/v1/visits/cosign-requirements/:user_id
+///
+/// **RBAC**: Requires 'manage_cosign' permission on 'visits' resource
+pub async fn set_cosign_requirement(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(user_id): Path<Uuid>,
+    Json(request): Json<SetCosignRequirementRequest>,
+) -> Result<Json<CosignRequirement>> {
+    check_permission(&state, &auth_user.role, "manage_cosign").await?;
+
+    let requirement = cosign_service(&state)?
+        .set_requirement(user_id, &request, auth_user.user_id)
+        .await?;
+
+    audit_requirement(
+        &state,
+        &auth_user,
+        &request_ctx,
+        user_id,
+        serde_json::json!({
+            "action": "cosign_requirement_set",
+            "supervisor_id": requirement.supervisor_id,
+        }),
+    )
+    .await;
+
+    Ok(Json(requirement))
+}
+
+/// Remove a user's co-signature requirement
+///
+/// DELETE /api/v1/visits/cosign-requirements/:user_id
+///
+/// **RBAC**: Requires 'manage_cosign' permission on 'visits' resource
+pub async fn delete_cosign_requirement(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(user_id): Path<Uuid>,
+) -> Result<StatusCode> {
+    check_permission(&state, &auth_user.role, "manage_cosign").await?;
+
+    cosign_service(&state)?.remove_requirement(user_id).await?;
+
+    audit_requirement(
+        &state,
+        &auth_user,
+        &request_ctx,
+        user_id,
+        serde_json::json!({ "action": "cosign_requirement_removed" }),
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
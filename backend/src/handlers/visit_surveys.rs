@@ -0,0 +1,73 @@
+/*!
+ * Visit Survey Handlers
+ *
+ * Public endpoint behind the link of post-visit survey emails. There is no
+ * sign-in: the signed survey token names the survey and the patient, and
+ * expires some days after the survey was sent.
+ *
+ * Endpoints:
+ * - POST /api/v1/surveys/respond - Answer a visit survey
+ */
+
+use axum::{extract::State, Extension, Json};
+use chrono::Utc;
+use validator::Validate;
+
+use crate::{
+    handlers::auth::AppState,
+    models::{
+        AuditAction, AuditLog, CreateAuditLog, EntityType, RequestContext,
+        VisitSurveyAnswerRequest, VisitSurveyAnswerResponse,
+    },
+    services::{NotificationService, VisitSurveyService},
+    utils::{AppError, Result},
+};
+
+/// Answer a visit survey
+///
+/// POST /api/v1/surveys/respond
+///
+/// A survey is answered once; answering again is a 409.
+pub async fn respond_to_visit_survey(
+    State(state): State<AppState>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Json(req): Json<VisitSurveyAnswerRequest>,
+) -> Result<Json<VisitSurveyAnswerResponse>> {
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let encryption_key = state
+        .encryption_key
+        .as_ref()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+
+    let claims =
+        NotificationService::verify_visit_survey_token(encryption_key, &req.token, Utc::now())
+            .ok_or_else(|| AppError::NotFound("This link is invalid or has expired".to_string()))?;
+
+    let response = VisitSurveyService::new(state.pool.clone(), encryption_key.clone())
+        .record_answer(&claims, req.score, req.comment.as_deref())
+        .await?;
+
+    // No staff user: the patient is in the payload; the comment stays out
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: None,
+            action: AuditAction::Update,
+            entity_type: EntityType::VisitSurvey,
+            entity_id: Some(claims.survey_id.to_string()),
+            changes: Some(serde_json::json!({
+                "source": "survey_link",
+                "patient_id": claims.patient_id,
+                "score": response.score,
+            })),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+
+    Ok(Json(response))
+}
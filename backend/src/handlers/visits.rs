@@ -7,7 +7,7 @@
 
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{header, HeaderMap, StatusCode},
     response::IntoResponse,
     Extension, Json,
 };
@@ -17,9 +17,15 @@ use validator::Validate;
 
 use crate::{
     handlers::auth::AppState,
-    models::{CreateVisitRequest, RequestContext, UpdateVisitRequest, UserRole, VisitResponse, VisitStatus, VisitType},
-    services::{VisitSearchFilter, VisitService},
-    utils::{AppError, Result},
+    models::{
+        CreateVisitRequest, RequestContext, UpdateVisitRequest, UserRole, VisitResponse,
+        VisitSearchQuery, VisitSearchResponse, VisitStatus, VisitType,
+    },
+    services::{VisitSearchFilter, VisitSearchService, VisitService},
+    utils::{
+        concurrency::{expected_version, version_tag},
+        AppError, Result,
+    },
 };
 use serde::Serialize;
 
@@ -78,9 +84,24 @@ async fn check_permission(
                 ));
             }
         }
-        _ => {
-            // Both ADMIN and DOCTOR can read/write visits
+        "sign" => {
+            // NURSE signs only under a co-signature requirement (enforced by the service and RLS)
+            if !matches!(user_role, UserRole::Admin | UserRole::Doctor | UserRole::Nurse) {
+                return Err(AppError::Forbidden(
+                    "Only doctors, administrators and supervised nurses can sign visits".to_string(),
+                ));
+            }
+        }
+        "lock" => {
             if !matches!(user_role, UserRole::Admin | UserRole::Doctor) {
+                return Err(AppError::Forbidden(
+                    "Only doctors and administrators can lock visits".to_string(),
+                ));
+            }
+        }
+        _ => {
+            // ADMIN and DOCTOR can read/write visits; NURSE works on drafts (enforced by RLS)
+            if !matches!(user_role, UserRole::Admin | UserRole::Doctor | UserRole::Nurse) {
                 return Err(AppError::Forbidden(
                     "Insufficient permissions".to_string(),
                 ));
@@ -122,7 +143,7 @@ pub struct AuthUser {
 /// POST /api/v1/visits
 ///
 /// **RBAC**: Requires 'create' permission on 'visits' resource
-/// **Roles**: ADMIN, DOCTOR
+/// **Roles**: ADMIN, DOCTOR, NURSE (DRAFT only)
 pub async fn create_visit(
     State(state): State<AppState>,
     Extension(user_id): Extension<Uuid>,
@@ -166,7 +187,7 @@ pub async fn create_visit(
 /// GET /api/v1/visits/:id
 ///
 /// **RBAC**: Requires 'read' permission on 'visits' resource
-/// **Roles**: ADMIN, DOCTOR
+/// **Roles**: ADMIN, DOCTOR, NURSE
 pub async fn get_visit(
     State(state): State<AppState>,
     Extension(user_id): Extension<Uuid>,
@@ -193,7 +214,7 @@ pub async fn get_visit(
         })?
         .ok_or_else(|| AppError::NotFound(format!("Visit {} not found", id)))?;
 
-    Ok(Json(visit))
+    Ok(([(header::ETAG, version_tag(visit.updated_at))], Json(visit)))
 }
 
 /// Update visit (only DRAFT visits can be updated)
@@ -201,15 +222,18 @@ pub async fn get_visit(
 /// PUT /api/v1/visits/:id
 ///
 /// **RBAC**: Requires 'update' permission on 'visits' resource
-/// **Roles**: ADMIN, DOCTOR
+/// **Roles**: ADMIN, DOCTOR, NURSE (DRAFT only)
 /// **Business Rule**: Only DRAFT visits can be edited
+/// **Concurrency**: With `If-Match` or `expected_version`, 409 with the
+/// current visit if it changed since
 pub async fn update_visit(
     State(state): State<AppState>,
     Extension(user_id): Extension<Uuid>,
     Extension(user_role): Extension<UserRole>,
     Extension(request_ctx): Extension<RequestContext>,
     Path(id): Path<Uuid>,
-    Json(req): Json<UpdateVisitRequest>,
+    headers: HeaderMap,
+    Json(mut req): Json<UpdateVisitRequest>,
 ) -> Result<impl IntoResponse> {
     // Check permissions
     check_permission(&state, &user_role,"update").await?;
@@ -217,6 +241,7 @@ pub async fn update_visit(
     // Validate request
     req.validate()
         .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+    req.expected_version = expected_version(&headers, req.expected_version)?;
 
     // Update visit service
     let encryption_key = state
@@ -228,17 +253,21 @@ pub async fn update_visit(
     let visit = visit_service
         .update_visit(id, req, user_id, Some(&request_ctx))
         .await
-        .map_err(|e| {
-            tracing::error!("Failed to update visit {}: {}", id, e);
-            // Check if error is about locked/signed status
-            if e.to_string().contains("Cannot edit visit") {
-                AppError::BadRequest(e.to_string())
-            } else {
-                AppError::Internal(format!("Failed to update visit: {}", e))
+        .map_err(|e| match e.downcast::<AppError>() {
+            // Edited by someone else since the client read it
+            Ok(app_error) => app_error,
+            Err(e) => {
+                tracing::error!("Failed to update visit {}: {}", id, e);
+                // Check if error is about locked/signed status
+                if e.to_string().contains("Cannot edit visit") {
+                    AppError::BadRequest(e.to_string())
+                } else {
+                    AppError::Internal(format!("Failed to update visit: {}", e))
+                }
             }
         })?;
 
-    Ok(Json(visit))
+    Ok(([(header::ETAG, version_tag(visit.updated_at))], Json(visit)))
 }
 
 /// Delete visit (only DRAFT visits can be deleted)
@@ -285,7 +314,7 @@ pub async fn delete_visit(
 /// GET /api/v1/visits?patient_id=...&status=...&limit=20&offset=0
 ///
 /// **RBAC**: Requires 'read' permission on 'visits' resource
-/// **Roles**: ADMIN, DOCTOR
+/// **Roles**: ADMIN, DOCTOR, NURSE
 pub async fn list_visits(
     State(state): State<AppState>,
     Extension(user_id): Extension<Uuid>,
@@ -358,12 +387,42 @@ pub async fn list_visits(
     Ok(Json(response))
 }
 
+/// Full-text search over visit notes
+///
+/// GET /api/v1/visits/search?q=carpal tunnel&patient_id=...&limit=20&offset=0
+///
+/// Finds visits whose narrative fields contain every word of `q` (whole
+/// words, case and accents ignored), best match first, with the matching
+/// passages highlighted.
+///
+/// **RBAC**: Requires 'read' permission on 'visits' resource
+/// **Roles**: ADMIN, DOCTOR, NURSE
+pub async fn search_visit_notes(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(user_role): Extension<UserRole>,
+    Query(query): Query<VisitSearchQuery>,
+) -> Result<Json<VisitSearchResponse>> {
+    check_permission(&state, &user_role, "read").await?;
+
+    let encryption_key = state
+        .encryption_key
+        .as_ref()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+
+    let results = VisitSearchService::new(state.pool.clone(), encryption_key.clone())
+        .search(&query, user_id)
+        .await?;
+
+    Ok(Json(results))
+}
+
 /// Get all visits for a specific patient
 ///
 /// GET /api/v1/patients/:patient_id/visits?limit=50&offset=0
 ///
 /// **RBAC**: Requires 'read' permission on 'visits' resource
-/// **Roles**: ADMIN, DOCTOR
+/// **Roles**: ADMIN, DOCTOR, NURSE
 pub async fn get_patient_visits(
     State(state): State<AppState>,
     Extension(user_id): Extension<Uuid>,
@@ -430,9 +489,11 @@ pub async fn get_patient_visits(
 ///
 /// POST /api/v1/visits/:id/sign
 ///
-/// **RBAC**: Requires 'update' permission on 'visits' resource
-/// **Roles**: ADMIN, DOCTOR (must be the provider or have override permission)
-/// **Business Rule**: Only DRAFT visits can be signed
+/// **RBAC**: Requires 'sign' permission on 'visits' resource
+/// **Roles**: ADMIN, DOCTOR (must be the provider or have override permission);
+/// NURSE under a co-signature requirement, for visits they documented
+/// **Business Rule**: Only DRAFT visits can be signed; a signer under
+/// co-signature sends the visit to PENDING_COSIGN instead of SIGNED
 pub async fn sign_visit(
     State(state): State<AppState>,
     Extension(user_id): Extension<Uuid>,
@@ -441,7 +502,7 @@ pub async fn sign_visit(
     Path(id): Path<Uuid>,
 ) -> Result<impl IntoResponse> {
     // Check permissions
-    check_permission(&state, &user_role,"update").await?;
+    check_permission(&state, &user_role, "sign").await?;
 
     // Sign visit service
     let encryption_key = state
@@ -469,7 +530,7 @@ pub async fn sign_visit(
 ///
 /// POST /api/v1/visits/:id/lock
 ///
-/// **RBAC**: Requires 'update' permission on 'visits' resource
+/// **RBAC**: Requires 'lock' permission on 'visits' resource
 /// **Roles**: ADMIN, DOCTOR
 /// **Business Rule**: Only SIGNED visits can be locked
 pub async fn lock_visit(
@@ -480,7 +541,7 @@ pub async fn lock_visit(
     Path(id): Path<Uuid>,
 ) -> Result<impl IntoResponse> {
     // Check permissions
-    check_permission(&state, &user_role,"update").await?;
+    check_permission(&state, &user_role, "lock").await?;
 
     // Lock visit service
     let encryption_key = state
@@ -509,7 +570,7 @@ pub async fn lock_visit(
 /// GET /api/v1/visits/statistics
 ///
 /// **RBAC**: Requires 'read' permission on 'visits' resource
-/// **Roles**: ADMIN, DOCTOR
+/// **Roles**: ADMIN, DOCTOR, NURSE
 pub async fn get_visit_statistics(
     State(state): State<AppState>,
     Extension(user_id): Extension<Uuid>,
@@ -0,0 +1,87 @@
+/*!
+ * Vitals Handlers
+ *
+ * Vital signs trends across a patient's visits.
+ *
+ * Endpoints:
+ * - GET /api/v1/patients/:id/vitals/trends - Vitals over time, with pediatric growth percentiles
+ */
+
+use axum::{
+    extract::{Path, Query, State},
+    Extension, Json,
+};
+use uuid::Uuid;
+
+use crate::{
+    handlers::auth::AppState,
+    models::{AuthUser, UserRole, VitalsTrendQuery, VitalsTrendResponse},
+    services::VitalsService,
+    utils::{AppError, Result},
+};
+
+#[cfg(feature = "rbac")]
+use tracing::warn;
+
+/// Check if user has permission to perform action on visits resource
+#[cfg(feature = "rbac")]
+async fn check_permission(state: &AppState, user_role: &UserRole, action: &str) -> Result<()> {
+    let has_permission = state
+        .enforcer
+        .enforce(user_role, "visits", action)
+        .await
+        .map_err(|e| {
+            warn!("RBAC enforcement error: {}", e);
+            AppError::Internal("Failed to check permissions".to_string())
+        })?;
+
+    if !has_permission {
+        return Err(AppError::Forbidden(format!(
+            "User does not have permission to {} visits",
+            action
+        )));
+    }
+
+    Ok(())
+}
+
+/// Fallback for non-RBAC builds - only checks role
+#[cfg(not(feature = "rbac"))]
+async fn check_permission(_state: &AppState, user_role: &UserRole, _action: &str) -> Result<()> {
+    if !matches!(
+        user_role,
+        UserRole::Admin | UserRole::Doctor | UserRole::Nurse
+    ) {
+        return Err(AppError::Forbidden("Insufficient permissions".to_string()));
+    }
+    Ok(())
+}
+
+/// Vitals trends of a patient
+///
+/// GET /api/v1/patients/:id/vitals/trends
+///
+/// Weight, height, BMI, blood pressure and the other vital signs of each
+/// visit that recorded them, oldest first. Visits before the patient turned
+/// 20 also carry weight, height and BMI percentiles for age.
+///
+/// **RBAC**: Requires 'read' permission on 'visits' resource
+pub async fn get_vitals_trends(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthUser>,
+    Path(patient_id): Path<Uuid>,
+    Query(query): Query<VitalsTrendQuery>,
+) -> Result<Json<VitalsTrendResponse>> {
+    check_permission(&state, &auth_user.role, "read").await?;
+
+    let encryption_key = state
+        .encryption_key
+        .clone()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+
+    let trends = VitalsService::new(state.pool.clone(), encryption_key)
+        .get_trends(patient_id, &query, auth_user.user_id)
+        .await?;
+
+    Ok(Json(trends))
+}
@@ -0,0 +1,83 @@
+/*!
+ * Waiting Room Handlers
+ *
+ * Queue of the patients who have arrived for their appointment and wait to
+ * be seen. Patients join it when they are checked in (status ARRIVED) and
+ * leave it when the visit starts (IN_PROGRESS).
+ *
+ * Endpoints:
+ * - GET /api/v1/appointments/queue - Today's (or a day's) waiting room queue
+ * - POST /api/v1/appointments/:id/check-in - Check the patient in
+ */
+
+use axum::{
+    extract::{Path, Query, State},
+    Extension, Json,
+};
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::{
+    handlers::{appointments::check_permission, auth::AppState},
+    models::{
+        AppointmentDto, CheckInRequest, RequestContext, UserRole, WaitingRoomQuery,
+        WaitingRoomQueue,
+    },
+    services::{AppointmentService, WaitingRoomService},
+    utils::{AppError, Result},
+};
+
+/// Get the waiting room queue
+///
+/// GET /api/v1/appointments/queue
+///
+/// Ordered by appointment time, then by waiting time. `provider_id` narrows
+/// it to one provider's patients; `date` picks another day than today.
+pub async fn get_waiting_room_queue(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(user_role): Extension<UserRole>,
+    Query(query): Query<WaitingRoomQuery>,
+) -> Result<Json<WaitingRoomQueue>> {
+    check_permission(&state, &user_role, "read").await?;
+
+    let queue = WaitingRoomService::new(state.pool.clone())
+        .queue(&query, user_id, Utc::now())
+        .await?;
+
+    Ok(Json(queue))
+}
+
+/// Check a patient in
+///
+/// POST /api/v1/appointments/:id/check-in
+///
+/// The appointment becomes ARRIVED and the patient joins the queue.
+/// `arrived_at` records an earlier arrival the same day; it defaults to now.
+pub async fn check_in_appointment(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(user_role): Extension<UserRole>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(id): Path<Uuid>,
+    Json(req): Json<CheckInRequest>,
+) -> Result<Json<AppointmentDto>> {
+    check_permission(&state, &user_role, "update").await?;
+
+    let encryption_key = state
+        .encryption_key
+        .as_ref()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+    let service = AppointmentService::new(state.pool.clone(), encryption_key.clone());
+
+    let appointment = service
+        .check_in(id, req.arrived_at, user_id, Some(&request_ctx))
+        .await
+        .map_err(|e| match e.downcast::<AppError>() {
+            Ok(app_error) => app_error,
+            Err(e) if e.to_string().contains("not found") => AppError::NotFound(e.to_string()),
+            Err(e) => AppError::Internal(e.to_string()),
+        })?;
+
+    Ok(Json(appointment))
+}
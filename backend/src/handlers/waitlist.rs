@@ -0,0 +1,225 @@
+/*!
+ * Waitlist Handlers
+ *
+ * Patients who want an earlier appointment. Staff put them on the waitlist;
+ * when an appointment is cancelled the waitlist job emails its slot to the
+ * matching patients, and the first to accept through the offer link gets
+ * the appointment. The offer link needs no sign-in: its signed token names
+ * the offer, the patient and the action.
+ *
+ * Endpoints:
+ * - GET /api/v1/appointments/waitlist - List waitlist entries
+ * - POST /api/v1/appointments/waitlist - Put a patient on the waitlist
+ * - DELETE /api/v1/appointments/waitlist/:id - Take a patient off the waitlist
+ * - GET /api/v1/appointments/waitlist/:id/offers - Slots offered to an entry
+ * - POST /api/v1/waitlist-offers/respond - Accept or decline from an offer link
+ */
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use chrono::Utc;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    handlers::{appointments::check_permission, auth::AppState},
+    models::{
+        AuditAction, AuditLog, CreateAuditLog, CreateWaitlistEntryRequest, EntityType,
+        RequestContext, UserRole, WaitlistEntry, WaitlistOffer, WaitlistOfferAction,
+        WaitlistOfferRequest, WaitlistOfferResponse, WaitlistOfferStatus, WaitlistQuery,
+    },
+    services::{NotificationService, WaitlistService},
+    utils::{AppError, Result},
+};
+
+fn waitlist_service(state: &AppState) -> Result<WaitlistService> {
+    let encryption_key = state
+        .encryption_key
+        .clone()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+
+    Ok(WaitlistService::new(state.pool.clone(), encryption_key))
+}
+
+/// Audit an operation on a waitlist entry
+async fn audit(
+    state: &AppState,
+    user_id: Uuid,
+    request_ctx: &RequestContext,
+    action: AuditAction,
+    entry: &WaitlistEntry,
+) {
+    let _ = AuditLog::create(
+        &state.pool,
+        CreateAuditLog {
+            user_id: Some(user_id),
+            action,
+            entity_type: EntityType::WaitlistEntry,
+            entity_id: Some(entry.id.to_string()),
+            changes: Some(serde_json::json!({
+                "patient_id": entry.patient_id,
+                "provider_id": entry.provider_id,
+                "status": entry.status,
+            })),
+            ip_address: request_ctx.ip_address.clone(),
+            user_agent: request_ctx.user_agent.clone(),
+            request_id: Some(request_ctx.request_id),
+        },
+    )
+    .await;
+}
+
+/// List waitlist entries
+///
+/// GET /api/v1/appointments/waitlist
+///
+/// Longest waiting first. `status` defaults to WAITING; `provider_id` also
+/// includes the patients waiting for any provider.
+pub async fn list_waitlist(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(user_role): Extension<UserRole>,
+    Query(query): Query<WaitlistQuery>,
+) -> Result<Json<Vec<WaitlistEntry>>> {
+    check_permission(&state, &user_role, "read").await?;
+
+    let entries = waitlist_service(&state)?.list(&query, user_id).await?;
+
+    Ok(Json(entries))
+}
+
+/// Put a patient on the waitlist
+///
+/// POST /api/v1/appointments/waitlist
+pub async fn create_waitlist_entry(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(user_role): Extension<UserRole>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Json(req): Json<CreateWaitlistEntryRequest>,
+) -> Result<(StatusCode, Json<WaitlistEntry>)> {
+    check_permission(&state, &user_role, "update").await?;
+
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let entry = waitlist_service(&state)?
+        .create(&req, user_id, Utc::now())
+        .await?;
+
+    audit(&state, user_id, &request_ctx, AuditAction::Create, &entry).await;
+
+    Ok((StatusCode::CREATED, Json(entry)))
+}
+
+/// Take a patient off the waitlist
+///
+/// DELETE /api/v1/appointments/waitlist/:id
+///
+/// The entry is kept as CANCELLED; the links of a pending offer stop
+/// working.
+pub async fn cancel_waitlist_entry(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(user_role): Extension<UserRole>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<WaitlistEntry>> {
+    check_permission(&state, &user_role, "update").await?;
+
+    let entry = waitlist_service(&state)?
+        .cancel(id, user_id, Utc::now())
+        .await?;
+
+    audit(&state, user_id, &request_ctx, AuditAction::Update, &entry).await;
+
+    Ok(Json(entry))
+}
+
+/// Slots offered to a waitlist entry
+///
+/// GET /api/v1/appointments/waitlist/:id/offers
+pub async fn list_waitlist_entry_offers(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(user_role): Extension<UserRole>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<WaitlistOffer>>> {
+    check_permission(&state, &user_role, "read").await?;
+
+    let offers = waitlist_service(&state)?.offers(id, user_id).await?;
+
+    Ok(Json(offers))
+}
+
+/// Accept or decline a slot from a waitlist offer link
+///
+/// POST /api/v1/waitlist-offers/respond
+///
+/// Accepting books the appointment unless another patient accepted first.
+/// Using a link again answers with the offer as it is.
+pub async fn respond_to_waitlist_offer(
+    State(state): State<AppState>,
+    Extension(request_ctx): Extension<RequestContext>,
+    Json(req): Json<WaitlistOfferRequest>,
+) -> Result<Json<WaitlistOfferResponse>> {
+    req.validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+
+    let encryption_key = state
+        .encryption_key
+        .as_ref()
+        .ok_or_else(|| AppError::Internal("Encryption key not configured".to_string()))?;
+
+    let now = Utc::now();
+    let claims = NotificationService::verify_waitlist_offer_token(encryption_key, &req.token, now)
+        .ok_or_else(|| AppError::NotFound("This link is invalid or has expired".to_string()))?;
+
+    let outcome = waitlist_service(&state)?.respond(&claims, now).await?;
+
+    // No staff user: the patient is in the payload
+    if let (true, Some(appointment_id)) = (outcome.changed, outcome.appointment_id) {
+        let _ = AuditLog::create(
+            &state.pool,
+            CreateAuditLog {
+                user_id: None,
+                action: AuditAction::Create,
+                entity_type: EntityType::Appointment,
+                entity_id: Some(appointment_id.to_string()),
+                changes: Some(serde_json::json!({
+                    "source": "waitlist_offer",
+                    "offer_id": outcome.offer_id,
+                    "patient_id": claims.patient_id,
+                })),
+                ip_address: request_ctx.ip_address.clone(),
+                user_agent: request_ctx.user_agent.clone(),
+                request_id: Some(request_ctx.request_id),
+            },
+        )
+        .await;
+    }
+
+    let message = match (claims.action, outcome.status) {
+        (_, WaitlistOfferStatus::Accepted) => "Thank you, your appointment is booked.",
+        (WaitlistOfferAction::Decline, WaitlistOfferStatus::Declined) => {
+            "Thank you for letting us know. You stay on the waitlist."
+        }
+        (_, WaitlistOfferStatus::Declined) => "You already declined this appointment.",
+        (_, WaitlistOfferStatus::Expired) => "This offer has expired.",
+        (_, WaitlistOfferStatus::Superseded | WaitlistOfferStatus::Pending) => {
+            "Sorry, this appointment is no longer available."
+        }
+    };
+
+    Ok(Json(WaitlistOfferResponse {
+        action: claims.action,
+        status: outcome.status,
+        slot_start: outcome.slot_start,
+        slot_end: outcome.slot_end,
+        provider_name: outcome.provider_name,
+        message: message.to_string(),
+    }))
+}
@@ -18,7 +18,8 @@ mod services;
 mod utils;
 
 use axum::{
-    http::StatusCode,
+    body::Body,
+    http::{Request, StatusCode},
     response::{IntoResponse, Json},
     routing::get,
     Router,
@@ -35,10 +36,18 @@ use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 use config::Config;
 use db::create_pool;
 use handlers::auth::AppState;
+use middleware::audit::redact_path;
+use middleware::compression::compression_layer;
 use middleware::cors::cors_from_env;
 use middleware::session_timeout::SessionManager;
-use routes::create_api_v1_routes;
-use services::{AuthService, EmailService, NotificationService, SettingsService, spawn_notification_scheduler};
+use middleware::ip_allowlist::AdminIpAllowlist;
+use middleware::rate_limit::{LoginThrottle, LoginThrottleConfig, RateLimitConfig, RateLimitLayer};
+use middleware::rate_limit_store::{build_rate_limit_store, RateLimitBackend};
+use middleware::token_denylist::{spawn_token_denylist_refresh, TokenDenylist};
+use routes::{
+    create_api_v1_routes, create_api_v2_routes, create_booking_v1_routes, create_portal_v1_routes,
+};
+use services::{AuthService, EmailService, NotificationService, ReferenceCache, SettingsService, SistemaTsClient, spawn_appointment_text_encryption_backfill, spawn_attachment_ocr_job, spawn_audit_chain_verifier, spawn_field_reencryption_job, spawn_janitor, spawn_medication_sync_job, spawn_patient_blind_index_backfill, spawn_patient_export_cleanup, spawn_notification_scheduler, spawn_rate_limit_quota_refresh, spawn_recall_job, spawn_report_subscription_job, spawn_report_view_refresh, spawn_retention_job, spawn_telemetry_reporter, spawn_visit_search_indexer, spawn_visit_transcription_job, spawn_waitlist_job};
 use std::sync::Arc;
 use utils::EncryptionKey;
 
@@ -91,7 +100,7 @@ async fn main() -> anyhow::Result<()> {
     tracing::info!("Rust version: {}", env!("CARGO_PKG_RUST_VERSION"));
 
     // Load configuration
-    let config = Config::from_env()?;
+    let config = Config::load().await?;
     tracing::info!("Configuration loaded successfully");
     tracing::info!("Environment: {}", config.server.environment);
 
@@ -107,6 +116,11 @@ async fn main() -> anyhow::Result<()> {
         );
     }
 
+    // Stream security events to the SIEM (if configured) before anything can emit them
+    if let Some(siem) = config.siem.clone() {
+        services::siem_exporter::init_siem_exporter(siem);
+    }
+
     // Create database connection pool
     let pool = create_pool(&config.database).await?;
     tracing::info!("Database connection pool created successfully");
@@ -122,13 +136,18 @@ async fn main() -> anyhow::Result<()> {
         config.security.session_timeout
     );
 
+    // Load revoked token IDs so revocations survive restarts
+    let token_denylist = TokenDenylist::load(&pool).await?;
+    tracing::info!("Token denylist loaded with {} revoked tokens", token_denylist.len());
+
     // Initialize Casbin enforcer for RBAC (if rbac feature is enabled)
     #[cfg(feature = "rbac")]
     let enforcer = {
         let model_path = "casbin/model.conf";
         let policy_path = "casbin/policy.csv";
 
-        match CasbinEnforcer::new(model_path, policy_path).await {
+        // Policies are stored in the database; policy.csv seeds an empty table
+        match CasbinEnforcer::with_database(model_path, policy_path, pool.clone()).await {
             Ok(enforcer) => {
                 tracing::info!("Casbin RBAC enforcer initialized successfully");
                 enforcer
@@ -141,9 +160,9 @@ async fn main() -> anyhow::Result<()> {
     };
 
     // Initialize encryption key for PHI/PII data
-    let encryption_key = match EncryptionKey::from_env() {
+    let encryption_key = match EncryptionKey::from_base64(&config.security.encryption_key) {
         Ok(key) => {
-            tracing::info!("Encryption key loaded successfully from environment");
+            tracing::info!("Encryption key loaded successfully");
             Some(key)
         }
         Err(e) => {
@@ -172,20 +191,90 @@ async fn main() -> anyhow::Result<()> {
         }
     };
 
+    // Initialize Sistema TS client (optional - for dematerialized prescriptions)
+    let sistema_ts = match config.sistema_ts.clone() {
+        Some(sistema_ts_config) => match SistemaTsClient::new(sistema_ts_config) {
+            Ok(client) => {
+                tracing::info!("Sistema TS e-prescription client initialized");
+                Some(client)
+            }
+            Err(e) => {
+                tracing::warn!("Failed to initialize Sistema TS client: {}. E-prescriptions will be unavailable.", e);
+                None
+            }
+        },
+        None => {
+            tracing::info!("Sistema TS not configured - e-prescriptions disabled");
+            None
+        }
+    };
+
+    // Initialize video room provider for televisits (optional)
+    let video_room_provider = match &config.video {
+        Some(video_config) => match services::build_video_room_provider(video_config) {
+            Ok(provider) => {
+                tracing::info!("Televisit video rooms provided by {}", provider.name());
+                Some(provider)
+            }
+            Err(e) => {
+                tracing::warn!("Failed to initialize video room provider: {:#}. Televisits will be unavailable.", e);
+                None
+            }
+        },
+        None => {
+            tracing::info!("Video provider not configured - televisits disabled");
+            None
+        }
+    };
+
     // Record server start time
     let start_time = std::time::SystemTime::now();
 
+    // Initialize reference data cache (in memory, or Redis if REDIS_ENABLED=true)
+    let reference_cache = ReferenceCache::from_env().await?;
+    tracing::info!("Reference data cache stored in {}", reference_cache.store_name());
+
     // Create settings service (shared singleton with cache)
-    let settings_service = Arc::new(SettingsService::new(pool.clone()));
+    let settings_service = Arc::new(SettingsService::with_cache(pool.clone(), reference_cache.clone()));
+
+    // Follow forwarding headers only through the configured reverse proxies
+    if config.trusted_proxies.is_empty() {
+        tracing::info!("No trusted proxies configured, client IP taken from the connection");
+    } else {
+        tracing::info!(
+            "Client IP resolved through {} trusted proxy range(s)",
+            config.trusted_proxies.len()
+        );
+    }
+    middleware::client_ip::init_trusted_proxies(config.trusted_proxies.clone());
+
+    if !config.admin_ip_allowlist.is_empty() {
+        tracing::info!(
+            "Admin routes restricted to {} configured IP range(s)",
+            config.admin_ip_allowlist.len()
+        );
+    }
+
+    // Initialize rate limit and failed login counters (in memory, or shared
+    // between replicas)
+    let rate_limit_store = build_rate_limit_store(&RateLimitBackend::from_env()?, &pool).await?;
+    tracing::info!("Rate limit counters stored in {}", rate_limit_store.name());
 
     // Create application state
     let app_state = AppState {
         pool: pool.clone(),
         auth_service,
         session_manager,
+        token_denylist,
+        login_throttle: LoginThrottle::with_store(LoginThrottleConfig::default(), rate_limit_store.clone()),
+        rate_limiter: RateLimitLayer::with_store(RateLimitConfig::from_env(), rate_limit_store),
+        admin_ip_allowlist: AdminIpAllowlist::new(config.admin_ip_allowlist.clone()),
         encryption_key,
         email_service,
+        sistema_ts,
+        video_room_provider,
         settings_service,
+        reference_cache,
         start_time,
         environment: config.server.environment.clone(),
         #[cfg(feature = "rbac")]
@@ -208,6 +297,126 @@ async fn main() -> anyhow::Result<()> {
         tracing::info!("Notification scheduler not started - encryption key not configured");
     }
 
+    // Spawn stuck-state janitor (fails notifications/documents stuck in transient states)
+    spawn_janitor(pool.clone(), app_state.settings_service.clone());
+
+    // Spawn periodic audit log hash chain verification (alerts on tampering)
+    spawn_audit_chain_verifier(pool.clone(), app_state.settings_service.clone());
+
+    // Spawn cleanup of expired GDPR patient export bundles
+    if let Some(ref enc_key) = app_state.encryption_key {
+        spawn_patient_export_cleanup(
+            pool.clone(),
+            enc_key.clone(),
+            std::path::PathBuf::from(
+                std::env::var("DOCUMENT_STORAGE_PATH").unwrap_or_else(|_| "./documents".to_string()),
+            ),
+        );
+    }
+
+    // Spawn blind index computation for patients saved before the indexes existed
+    if let Some(ref enc_key) = app_state.encryption_key {
+        spawn_patient_blind_index_backfill(pool.clone(), enc_key.clone());
+    }
+
+    // Spawn deterministic re-encryption of fiscal codes and policy numbers saved before it existed
+    if let Some(ref enc_key) = app_state.encryption_key {
+        spawn_field_reencryption_job(pool.clone(), enc_key.clone());
+    }
+
+    // Spawn encryption of appointment text saved before it was encrypted
+    if let Some(ref enc_key) = app_state.encryption_key {
+        spawn_appointment_text_encryption_backfill(pool.clone(), enc_key.clone());
+    }
+
+    // Spawn indexing for search of visit notes saved before the search index existed
+    if let Some(ref enc_key) = app_state.encryption_key {
+        spawn_visit_search_indexer(pool.clone(), enc_key.clone());
+    }
+
+    // Spawn OCR of patient attachments (if an OCR provider and encryption are configured)
+    if let (Some(ref ocr), Some(ref enc_key)) = (&config.ocr, &app_state.encryption_key) {
+        match services::ocr_engine::build_ocr_engine(ocr) {
+            Ok(engine) => spawn_attachment_ocr_job(pool.clone(), enc_key.clone(), engine),
+            Err(e) => tracing::error!("Attachment OCR not started: {:#}", e),
+        }
+    } else if config.ocr.is_some() {
+        tracing::info!("Attachment OCR not started - encryption key not configured");
+    }
+
+    // Spawn transcription of visit audio memos (if a transcription provider and encryption are configured)
+    if let (Some(ref transcription), Some(ref enc_key)) = (&config.transcription, &app_state.encryption_key) {
+        match services::transcription::build_transcription_engine(transcription) {
+            Ok(engine) => spawn_visit_transcription_job(pool.clone(), enc_key.clone(), engine),
+            Err(e) => tracing::error!("Visit transcription not started: {:#}", e),
+        }
+    } else if config.transcription.is_some() {
+        tracing::info!("Visit transcription not started - encryption key not configured");
+    }
+
+    // Spawn the reload of tokens revoked through other instances (every 15 seconds)
+    spawn_token_denylist_refresh(pool.clone(), app_state.token_denylist.clone());
+
+    // Spawn the reload of per-user rate limit quotas and the pruning of past
+    // rate limit windows (once at startup, then every minute)
+    spawn_rate_limit_quota_refresh(pool.clone(), app_state.rate_limiter.clone());
+
+    // Spawn scheduled data retention (applies enabled retention rules)
+    spawn_retention_job(pool.clone(), app_state.settings_service.clone());
+
+    // Spawn scheduled AIFA medication re-sync (interval from settings, 0 disables)
+    spawn_medication_sync_job(
+        pool.clone(),
+        app_state.settings_service.clone(),
+        app_state.reference_cache.clone(),
+    );
+
+    // Spawn refresh of the materialized views behind the reports (interval
+    // from settings, 0 disables; reports are computed live when views are stale)
+    spawn_report_view_refresh(pool.clone(), app_state.settings_service.clone());
+
+    // Spawn delivery of scheduled report subscriptions (if email is enabled)
+    if let Some(ref email_svc) = app_state.email_service {
+        spawn_report_subscription_job(
+            pool.clone(),
+            email_svc.clone(),
+            app_state.encryption_key.clone(),
+        );
+    } else {
+        tracing::info!("Report subscriptions not delivered - email service not configured");
+    }
+
+    // Spawn scheduled recall runs (needs email to send reminders and the
+    // encryption key to read patient data)
+    match (&app_state.email_service, &app_state.encryption_key) {
+        (Some(email_svc), Some(key)) => {
+            spawn_recall_job(pool.clone(), email_svc.clone(), key.clone());
+        }
+        _ => tracing::info!("Recalls not run - email service or encryption key not configured"),
+    }
+
+    // Spawn the waitlist job offering cancelled slots (same needs as recalls)
+    match (&app_state.email_service, &app_state.encryption_key) {
+        (Some(email_svc), Some(key)) => {
+            spawn_waitlist_job(
+                pool.clone(),
+                email_svc.clone(),
+                key.clone(),
+                app_state.settings_service.clone(),
+            );
+        }
+        _ => tracing::info!(
+            "Waitlist offers not sent - email service or encryption key not configured"
+        ),
+    }
+
+    // Spawn opt-in anonymous telemetry reporter (sends nothing until enabled in settings)
+    spawn_telemetry_reporter(
+        pool.clone(),
+        app_state.settings_service.clone(),
+        app_state.environment.clone(),
+    );
+
     // Build application router
     let app = create_app(app_state, start_time);
 
@@ -279,11 +488,42 @@ fn create_app(state: AppState, start_time: std::time::SystemTime) -> Router {
         .route("/api/version", get(version_handler))
         // Root endpoint
         .route("/", get(root_handler))
+        // Public keys for verifying access tokens
+        .route(
+            "/.well-known/jwks.json",
+            get(handlers::jwks_handler).with_state(state.clone()),
+        )
         // API v1 routes
-        .nest("/api/v1", create_api_v1_routes(state))
+        .nest("/api/v1", create_api_v1_routes(state.clone()))
+        // API v2 routes (v1 routes with the v2 response envelope)
+        .nest("/api/v2", create_api_v2_routes(state.clone()))
+        // Patient portal routes (portal tokens only, separate from staff RBAC)
+        .nest("/api/portal/v1", create_portal_v1_routes(state.clone()))
+        // Public self-booking routes (no sign-in, CAPTCHA and email verification)
+        .nest("/api/booking/v1", create_booking_v1_routes(state))
+        // Compress responses (brotli/gzip), skipping small bodies and binary downloads
+        .layer(compression_layer())
         // Add middleware (CORS must be added before other middleware)
         .layer(cors_from_env())
-        .layer(TraceLayer::new_for_http())
+        .layer(TraceLayer::new_for_http().make_span_with(make_request_span))
+}
+
+/// Span of a traced HTTP request, without the token of calendar feed URLs
+fn make_request_span(request: &Request<Body>) -> tracing::Span {
+    let path = request.uri().path();
+    let redacted = redact_path(path);
+    let uri = if redacted == path {
+        request.uri().to_string()
+    } else {
+        redacted.to_string()
+    };
+
+    tracing::debug_span!(
+        "request",
+        method = %request.method(),
+        uri = %uri,
+        version = ?request.version(),
+    )
 }
 
 /// Root handler - API information
@@ -296,6 +536,9 @@ async fn root_handler() -> impl IntoResponse {
         "endpoints": {
             "health": "/health",
             "api_v1": "/api/v1",
+            "api_v2": "/api/v2",
+            "portal": "/api/portal/v1",
+            "booking": "/api/booking/v1",
             "auth": "/api/v1/auth"
         }
     }))
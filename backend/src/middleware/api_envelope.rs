@@ -0,0 +1,301 @@
+/*!
+ * API v2 Response Envelope
+ *
+ * `/api/v2` serves the same routes as `/api/v1`; this layer rewrites their
+ * JSON responses into one envelope, so v1 clients keep working while
+ * clients move over:
+ *
+ * ```json
+ * { "data": ..., "meta": { "request_id": "...", "pagination": { "total": 150, "limit": 20, "offset": 0 } } }
+ * { "error": { "code": "NOT_FOUND", "message": "...", "details": { ... } }, "meta": { "request_id": "..." } }
+ * ```
+ *
+ * List responses (`{"patients": [...], "total": .., "limit": .., ...}`)
+ * become `data: [...]` with the counters in `meta.pagination`. Error codes
+ * are those of `AppError`; fields of v1 error bodies other than the code
+ * and message (e.g. `current` of a version conflict) go to `details`.
+ *
+ * Non-JSON responses (PDF, CSV, ZIP downloads, event streams) and empty
+ * bodies are passed through unchanged.
+ */
+
+use axum::{
+    body::{to_bytes, Body, HttpBody},
+    extract::Request,
+    http::{header, HeaderMap, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use serde_json::{json, Map, Value};
+
+use crate::models::request_context::REQUEST_ID_HEADER;
+use crate::utils::AppError;
+
+/// Largest JSON body wrapped in the envelope
+const MAX_ENVELOPE_BODY_BYTES: usize = 64 * 1024 * 1024;
+
+/// Keys of v1 list responses that describe the page
+const PAGINATION_KEYS: &[&str] = &[
+    "total",
+    "limit",
+    "offset",
+    "page",
+    "page_size",
+    "total_pages",
+];
+
+/// Keys of v1 error bodies that are not error details
+const ERROR_ENVELOPE_KEYS: &[&str] = &["error", "message", "request_id", "timestamp"];
+
+/// Wrap the JSON responses of the v1 routes in the v2 envelope
+pub async fn api_v2_envelope(request: Request, next: Next) -> Response {
+    let response = next.run(request).await;
+    let (mut parts, body) = response.into_parts();
+
+    let wrap = is_json(&parts.headers)
+        && body
+            .size_hint()
+            .exact()
+            .is_some_and(|len| len > 0 && len <= MAX_ENVELOPE_BODY_BYTES as u64);
+    if !wrap {
+        return Response::from_parts(parts, body);
+    }
+
+    let bytes = match to_bytes(body, MAX_ENVELOPE_BODY_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::error!("Failed to buffer response for the v2 envelope: {}", e);
+            parts.headers.remove(header::CONTENT_LENGTH);
+            return Response::from_parts(parts, Body::empty());
+        }
+    };
+    let Ok(value) = serde_json::from_slice::<Value>(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+
+    let request_id = parts
+        .headers
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+
+    let envelope = if parts.status.is_client_error() || parts.status.is_server_error() {
+        error_envelope(parts.status, value, request_id)
+    } else {
+        success_envelope(value, request_id)
+    };
+
+    match serde_json::to_vec(&envelope) {
+        Ok(rewritten) => {
+            parts.headers.remove(header::CONTENT_LENGTH);
+            Response::from_parts(parts, Body::from(rewritten))
+        }
+        Err(e) => {
+            tracing::error!("Failed to serialize the v2 envelope: {}", e);
+            Response::from_parts(parts, Body::from(bytes))
+        }
+    }
+}
+
+/// Whether the response carries JSON
+fn is_json(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|ct| ct.contains("application/json"))
+}
+
+/// Envelope of a successful response
+fn success_envelope(body: Value, request_id: Option<String>) -> Value {
+    let mut meta = Map::new();
+    if let Some(request_id) = request_id {
+        meta.insert("request_id".to_string(), Value::String(request_id));
+    }
+
+    let data = match split_page(body) {
+        Ok((items, pagination)) => {
+            meta.insert("pagination".to_string(), Value::Object(pagination));
+            items
+        }
+        Err(body) => body,
+    };
+
+    json!({ "data": data, "meta": meta })
+}
+
+/// Split a v1 list response into its items and page counters
+///
+/// A list response is an object with `total`, exactly one array and
+/// otherwise only page counters. Anything else is returned as is.
+fn split_page(body: Value) -> std::result::Result<(Value, Map<String, Value>), Value> {
+    let Value::Object(object) = body else {
+        return Err(body);
+    };
+
+    let arrays = object.values().filter(|v| v.is_array()).count();
+    let is_page = object.contains_key("total")
+        && arrays == 1
+        && object
+            .iter()
+            .all(|(key, value)| value.is_array() || PAGINATION_KEYS.contains(&key.as_str()));
+    if !is_page {
+        return Err(Value::Object(object));
+    }
+
+    let mut items = Value::Null;
+    let mut pagination = Map::new();
+    for (key, value) in object {
+        if value.is_array() {
+            items = value;
+        } else {
+            pagination.insert(key, value);
+        }
+    }
+
+    Ok((items, pagination))
+}
+
+/// Envelope of an error response
+fn error_envelope(status: StatusCode, body: Value, request_id: Option<String>) -> Value {
+    let mut object = match body {
+        Value::Object(object) => object,
+        other => {
+            let mut object = Map::new();
+            object.insert("details".to_string(), other);
+            object
+        }
+    };
+
+    let code = match object.get("error") {
+        Some(Value::String(code)) => code.clone(),
+        _ => AppError::code_for_status(status).to_string(),
+    };
+    let message = match object.get("message") {
+        Some(Value::String(message)) => message.clone(),
+        _ => status.canonical_reason().unwrap_or("Error").to_string(),
+    };
+    let request_id = request_id.or_else(|| {
+        object
+            .get("request_id")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+    });
+
+    object.retain(|key, _| !ERROR_ENVELOPE_KEYS.contains(&key.as_str()));
+
+    let mut error = Map::new();
+    error.insert("code".to_string(), Value::String(code));
+    error.insert("message".to_string(), Value::String(message));
+    if !object.is_empty() {
+        error.insert("details".to_string(), Value::Object(object));
+    }
+
+    let mut meta = Map::new();
+    if let Some(request_id) = request_id {
+        meta.insert("request_id".to_string(), Value::String(request_id));
+    }
+
+    json!({ "error": error, "meta": meta })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{middleware::from_fn, routing::get, Json, Router};
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_success_envelope_wraps_resource() {
+        let envelope = success_envelope(
+            json!({ "id": "abc", "first_name": "Mario" }),
+            Some("req-1".to_string()),
+        );
+
+        assert_eq!(envelope["data"]["first_name"], "Mario");
+        assert_eq!(envelope["meta"]["request_id"], "req-1");
+        assert!(envelope["meta"].get("pagination").is_none());
+    }
+
+    #[test]
+    fn test_success_envelope_moves_pagination_to_meta() {
+        let envelope = success_envelope(
+            json!({ "patients": [{ "id": "a" }, { "id": "b" }], "total": 150, "limit": 20, "offset": 0 }),
+            None,
+        );
+
+        assert_eq!(envelope["data"].as_array().unwrap().len(), 2);
+        assert_eq!(envelope["meta"]["pagination"]["total"], 150);
+        assert_eq!(envelope["meta"]["pagination"]["limit"], 20);
+    }
+
+    #[test]
+    fn test_success_envelope_keeps_other_objects() {
+        // Two arrays, or keys other than page counters: not a list response
+        let body = json!({ "total": 2, "by_status": [], "by_type": [] });
+        assert_eq!(success_envelope(body.clone(), None)["data"], body);
+
+        let body = json!({ "total": 1, "items": [], "generated_at": "2026-04-01" });
+        assert_eq!(success_envelope(body.clone(), None)["data"], body);
+    }
+
+    #[test]
+    fn test_error_envelope_moves_extra_fields_to_details() {
+        let envelope = error_envelope(
+            StatusCode::CONFLICT,
+            json!({
+                "error": "VERSION_CONFLICT",
+                "message": "The record was modified by another user",
+                "current": { "id": "abc" },
+                "timestamp": "2026-04-01T10:00:00Z",
+                "request_id": "req-1",
+            }),
+            None,
+        );
+
+        assert_eq!(envelope["error"]["code"], "VERSION_CONFLICT");
+        assert_eq!(envelope["error"]["details"]["current"]["id"], "abc");
+        assert!(envelope["error"]["details"].get("timestamp").is_none());
+        assert_eq!(envelope["meta"]["request_id"], "req-1");
+    }
+
+    #[test]
+    fn test_error_envelope_without_code() {
+        let envelope = error_envelope(StatusCode::PAYLOAD_TOO_LARGE, json!({}), None);
+
+        assert_eq!(envelope["error"]["code"], "PAYLOAD_TOO_LARGE");
+        assert_eq!(envelope["error"]["message"], "Payload Too Large");
+        assert!(envelope["error"].get("details").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_api_v2_envelope_layer() {
+        let app = Router::new()
+            .route("/json", get(|| async { Json(json!({ "id": 1 })) }))
+            .route("/text", get(|| async { "plain" }))
+            .layer(from_fn(api_v2_envelope));
+
+        let response = app
+            .clone()
+            .oneshot(
+                axum::http::Request::get("/json")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let value: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(value["data"]["id"], 1);
+
+        let response = app
+            .oneshot(
+                axum::http::Request::get("/text")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&bytes[..], b"plain");
+    }
+}
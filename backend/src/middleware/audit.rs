@@ -11,6 +11,8 @@
  * - Request/response status tracking
  * - Immutable audit trail (enforced by database)
  * - Performance optimized (async logging)
+ * - Domain events for mutating requests (see `models::domain_event`)
+ * - 401 and 403 responses streamed to the SIEM (see `services::siem_exporter`)
  *
  * HIPAA Compliance:
  * - 45 CFR § 164.312(b) - Audit Controls
@@ -19,17 +21,30 @@
  */
 
 use axum::{
-    extract::{Request, State},
+    body::{to_bytes, Body, HttpBody},
+    extract::{ConnectInfo, OriginalUri, Request, State},
+    http::{header, Method},
     middleware::Next,
     response::Response,
 };
 use ipnetwork::IpNetwork;
 use serde_json::Value;
 use sqlx::PgPool;
+use std::net::SocketAddr;
 use std::time::Instant;
 use uuid::Uuid;
 
 use crate::handlers::auth::AppState;
+use crate::middleware::client_ip::client_ip;
+use crate::models::domain_event::{
+    strip_api_prefix, with_domain_event_scope, DomainEvent, DomainEventOptOut, DomainEventRecorder,
+    DomainEventTarget,
+};
+use crate::models::RequestContext;
+use crate::services::siem_exporter::{self, SecurityEvent, SecurityEventKind};
+
+/// Largest response body captured as the "after" state of a domain event
+const MAX_EVENT_BODY_BYTES: u64 = 256 * 1024;
 
 /// Audit log entry that will be stored in the database
 #[derive(Debug, Clone)]
@@ -52,6 +67,10 @@ pub struct AuditLogEntry {
     pub status_code: u16,
     /// Duration of the request in milliseconds
     pub duration_ms: i64,
+    /// Correlation ID assigned by the request context middleware
+    pub request_id: Option<Uuid>,
+    /// Admin acting as `user_id` (impersonation sessions only)
+    pub impersonator_id: Option<Uuid>,
 }
 
 impl AuditLogEntry {
@@ -61,7 +80,8 @@ impl AuditLogEntry {
         user_id: Option<Uuid>,
         ip_address: Option<IpNetwork>,
     ) -> Self {
-        let path = request.uri().path();
+        let full_path = request_path(request);
+        let path = redact_path(&full_path);
         let method = request.method().as_str();
 
         // Extract entity type and ID from path
@@ -78,6 +98,12 @@ impl AuditLogEntry {
             .and_then(|v| v.to_str().ok())
             .map(|s| s.to_string());
 
+        // Correlation ID (set by request_context_middleware, which runs first)
+        let request_id = request
+            .extensions()
+            .get::<RequestContext>()
+            .map(|ctx| ctx.request_id);
+
         Self {
             user_id,
             action,
@@ -88,6 +114,8 @@ impl AuditLogEntry {
             user_agent,
             status_code: 0, // Will be set after response
             duration_ms: 0, // Will be set after response
+            request_id,
+            impersonator_id: None,
         }
     }
 
@@ -99,7 +127,7 @@ impl AuditLogEntry {
         self.status_code = status_code;
         self.duration_ms = duration_ms;
 
-        sqlx::query!(
+        sqlx::query(
             r#"
             INSERT INTO audit_logs (
                 user_id,
@@ -108,17 +136,21 @@ impl AuditLogEntry {
                 entity_id,
                 changes,
                 ip_address,
-                user_agent
-            ) VALUES ($1, $2, $3, $4, $5, $6, $7)
+                user_agent,
+                request_id,
+                impersonator_id
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
             "#,
-            self.user_id,
-            self.action,
-            self.entity_type,
-            self.entity_id,
-            self.changes,
-            self.ip_address,
-            self.user_agent,
         )
+        .bind(self.user_id)
+        .bind(&self.action)
+        .bind(&self.entity_type)
+        .bind(&self.entity_id)
+        .bind(&self.changes)
+        .bind(self.ip_address)
+        .bind(&self.user_agent)
+        .bind(self.request_id)
+        .bind(self.impersonator_id)
         .execute(pool)
         .await?;
 
@@ -130,22 +162,46 @@ impl AuditLogEntry {
 ///
 /// Examples:
 /// - `/api/v1/patients/123` -> ("patients", Some("123"))
-/// - `/api/v1/appointments` -> ("appointments", None)
+/// - `/api/v2/appointments` -> ("appointments", None)
 /// - `/health` -> ("SYSTEM", None)
 fn extract_entity_from_path(path: &str) -> (String, Option<String>) {
-    let parts: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
-
-    // Look for API paths: /api/v1/{entity_type}/{entity_id?}
-    if parts.len() >= 3 && parts[0] == "api" && parts[1] == "v1" {
-        let entity_type = parts[2].to_string();
-        let entity_id = parts.get(3).map(|s| s.to_string());
-        return (entity_type, entity_id);
+    // Look for API paths: /api/v{1,2}/{entity_type}/{entity_id?}
+    let parts: Vec<&str> = strip_api_prefix(path)
+        .map(|rest| rest.split('/').filter(|s| !s.is_empty()).collect())
+        .unwrap_or_default();
+    if let Some(entity_type) = parts.first() {
+        let entity_id = parts.get(1).map(|s| s.to_string());
+        return (entity_type.to_string(), entity_id);
     }
 
     // Fallback for non-API paths (should rarely be reached due to middleware skip)
     ("SYSTEM".to_string(), None)
 }
 
+/// Full request path, including the prefix of any router it is nested in
+///
+/// The audit layer sits on the API router, which is nested at `/api/v1` and
+/// `/api/v2`; axum strips that prefix from `request.uri()`.
+pub(crate) fn request_path(request: &Request) -> String {
+    request.extensions().get::<OriginalUri>().map_or_else(
+        || request.uri().path().to_string(),
+        |uri| uri.path().to_string(),
+    )
+}
+
+/// Path as written to the audit log and request traces
+///
+/// Calendar feed URLs carry their token, a credential, in the path.
+pub(crate) fn redact_path(path: &str) -> &str {
+    if path.starts_with("/api/v1/calendar/") {
+        "/api/v1/calendar/{token}.ics"
+    } else if path.starts_with("/api/v2/calendar/") {
+        "/api/v2/calendar/{token}.ics"
+    } else {
+        path
+    }
+}
+
 /// Audit logging middleware
 ///
 /// This middleware logs all API requests to the audit_logs table.
@@ -165,7 +221,7 @@ pub async fn audit_middleware(
     request: Request,
     next: Next,
 ) -> Response {
-    let path = request.uri().path().to_string();
+    let path = request_path(&request);
 
     // Skip audit logging for non-sensitive paths and non-API routes
     if path == "/health"
@@ -173,39 +229,37 @@ pub async fn audit_middleware(
         || path == "/api/health"
         || path == "/api/version"
         || path == "/"
-        || !path.starts_with("/api/v1/")
+        || strip_api_prefix(&path).is_none()
     {
         return next.run(request).await;
     }
 
-    // Extract user ID from JWT Authorization header (if present)
-    let user_id_value = extract_user_id_from_auth_header(&request, &state);
+    // Extract user ID (and impersonating admin) from JWT Authorization header (if present)
+    let (user_id_value, impersonator_id) = extract_user_from_auth_header(&request, &state)
+        .map_or((None, None), |(user_id, impersonator_id)| (Some(user_id), impersonator_id));
 
-    // Extract IP address from request headers
-    // Priority: X-Forwarded-For > X-Real-IP (behind reverse proxy, these are always set)
-    let ip_address = request
-        .headers()
-        .get("x-forwarded-for")
-        .and_then(|v| v.to_str().ok())
-        .and_then(|s| s.split(',').next())
-        .and_then(|s| s.trim().parse::<std::net::IpAddr>().ok())
-        .or_else(|| {
-            request
-                .headers()
-                .get("x-real-ip")
-                .and_then(|v| v.to_str().ok())
-                .and_then(|s| s.parse::<std::net::IpAddr>().ok())
-        })
-        .map(|ip| IpNetwork::from(ip));
+    // Resolve the client IP through the trusted proxies
+    let ip_address = client_ip(
+        request.headers(),
+        request.extensions().get::<ConnectInfo<SocketAddr>>(),
+    )
+    .map(IpNetwork::from);
 
     // Create audit log entry
-    let audit_entry = AuditLogEntry::from_request(&request, user_id_value, ip_address);
+    let mut audit_entry = AuditLogEntry::from_request(&request, user_id_value, ip_address);
+    audit_entry.impersonator_id = impersonator_id;
+
+    // Mutating requests also produce a domain event
+    let event_target = is_mutating(request.method())
+        .then(|| DomainEventTarget::from_request(request.method().as_str(), &path))
+        .flatten();
+    let recorder = DomainEventRecorder::new();
 
     // Record start time
     let start_time = Instant::now();
 
     // Process the request
-    let response = next.run(request).await;
+    let response = with_domain_event_scope(recorder.clone(), next.run(request)).await;
 
     // Calculate duration
     let duration_ms = start_time.elapsed().as_millis() as i64;
@@ -213,6 +267,46 @@ pub async fn audit_middleware(
     // Get response status code
     let status_code = response.status().as_u16();
 
+    // Authentication failures and permission denials go to the SIEM
+    if let Some(kind) = security_event_kind(status_code).filter(|_| siem_exporter::is_enabled()) {
+        siem_exporter::emit(SecurityEvent {
+            user_id: audit_entry.user_id,
+            ip_address: audit_entry.ip_address.map(|ip| ip.ip().to_string()),
+            request_id: audit_entry.request_id,
+            entity_type: Some(audit_entry.entity_type.clone()),
+            entity_id: audit_entry.entity_id.clone(),
+            status: Some(status_code),
+            ..SecurityEvent::new(
+                kind,
+                audit_entry.action.clone(),
+                format!("Request rejected with status {}", status_code),
+            )
+        });
+    }
+
+    // Domain event, unless the handler audited the change itself or the route opted out
+    let (response, event) = match event_target {
+        Some(target)
+            if response.status().is_success()
+                && !recorder.has_explicit_audit()
+                && response.extensions().get::<DomainEventOptOut>().is_none() =>
+        {
+            let (response, after) = capture_json_body(response).await;
+            let event = DomainEvent {
+                target,
+                user_id: audit_entry.user_id,
+                impersonator_id: audit_entry.impersonator_id,
+                ip_address: audit_entry.ip_address,
+                user_agent: audit_entry.user_agent.clone(),
+                request_id: audit_entry.request_id,
+                before: recorder.take_before(),
+                after,
+            };
+            (response, Some(event))
+        }
+        _ => (response, None),
+    };
+
     // Save audit log asynchronously (don't wait for it to complete)
     // This prevents audit logging from slowing down requests
     let pool = state.pool.clone();
@@ -220,19 +314,81 @@ pub async fn audit_middleware(
         if let Err(e) = audit_entry.save(&pool, status_code, duration_ms).await {
             tracing::error!("Failed to save audit log ({})", e.to_string().chars().take(100).collect::<String>());
         }
+        if let Some(event) = event {
+            if let Err(e) = event.save(&pool).await {
+                tracing::error!("Failed to save domain event ({})", e.to_string().chars().take(100).collect::<String>());
+            }
+        }
     });
 
     response
 }
 
-/// Extract user ID from JWT Authorization header without requiring auth middleware.
+/// Security event raised by a response status, if any
+fn security_event_kind(status_code: u16) -> Option<SecurityEventKind> {
+    match status_code {
+        401 => Some(SecurityEventKind::AuthFailure),
+        403 => Some(SecurityEventKind::PermissionDenied),
+        _ => None,
+    }
+}
+
+/// Whether a request method changes state
+fn is_mutating(method: &Method) -> bool {
+    matches!(*method, Method::POST | Method::PUT | Method::PATCH | Method::DELETE)
+}
+
+/// Copy a small JSON response body for the domain event
+///
+/// Streaming, non-JSON and oversized bodies are passed through untouched.
+async fn capture_json_body(response: Response) -> (Response, Option<serde_json::Value>) {
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|ct| ct.contains("application/json"));
+    let small = response
+        .body()
+        .size_hint()
+        .exact()
+        .is_some_and(|len| len > 0 && len <= MAX_EVENT_BODY_BYTES);
+    if !is_json || !small {
+        return (response, None);
+    }
+
+    let (parts, body) = response.into_parts();
+    match to_bytes(body, MAX_EVENT_BODY_BYTES as usize).await {
+        Ok(bytes) => {
+            let json = serde_json::from_slice(&bytes).ok();
+            (Response::from_parts(parts, Body::from(bytes)), json)
+        }
+        Err(e) => {
+            tracing::error!("Failed to buffer response for domain event: {}", e);
+            (Response::from_parts(parts, Body::empty()), None)
+        }
+    }
+}
+
+/// Opt a route out of automatic domain events
+///
+/// For routes that carry credentials or only read despite using POST:
+/// `.route_layer(middleware::from_fn(skip_domain_events))`
+pub async fn skip_domain_events(request: Request, next: Next) -> Response {
+    let mut response = next.run(request).await;
+    response.extensions_mut().insert(DomainEventOptOut);
+    response
+}
+
+/// Extract user ID and impersonating admin from JWT Authorization header without
+/// requiring auth middleware.
 /// Returns None if no valid token is present (unauthenticated requests).
-fn extract_user_id_from_auth_header(request: &Request, state: &AppState) -> Option<Uuid> {
+pub(crate) fn extract_user_from_auth_header(request: &Request, state: &AppState) -> Option<(Uuid, Option<Uuid>)> {
     let auth_header = request.headers().get("authorization")?;
     let auth_str = auth_header.to_str().ok()?;
     let token = auth_str.strip_prefix("Bearer ")?;
     let claims = state.auth_service.validate_token(token).ok()?;
-    Uuid::parse_str(&claims.sub).ok()
+    let user_id = Uuid::parse_str(&claims.sub).ok()?;
+    Some((user_id, claims.impersonator_id()))
 }
 
 
@@ -259,6 +415,11 @@ mod tests {
         assert_eq!(entity_type, "patients");
         assert_eq!(entity_id, Some("123".to_string()));
 
+        // Test API v2 path
+        let (entity_type, entity_id) = extract_entity_from_path("/api/v2/holidays/7");
+        assert_eq!(entity_type, "holidays");
+        assert_eq!(entity_id, Some("7".to_string()));
+
         // Test non-API path falls back to SYSTEM
         let (entity_type, entity_id) = extract_entity_from_path("/health");
         assert_eq!(entity_type, "SYSTEM");
@@ -287,6 +448,39 @@ mod tests {
         assert_eq!(entry.ip_address, ip_address);
     }
 
+    #[test]
+    fn test_audit_log_entry_uses_original_uri() {
+        // Inside the nested API router the URI has lost its /api/v1 prefix
+        let mut request = Request::builder()
+            .method(Method::POST)
+            .uri("/holidays")
+            .body(Body::empty())
+            .unwrap();
+        request
+            .extensions_mut()
+            .insert(OriginalUri("/api/v1/holidays".parse().unwrap()));
+
+        let entry = AuditLogEntry::from_request(&request, None, None);
+
+        assert_eq!(entry.action, "POST /api/v1/holidays");
+        assert_eq!(entry.entity_type, "holidays");
+    }
+
+    #[test]
+    fn test_audit_log_entry_redacts_calendar_feed_token() {
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri("/api/v1/calendar/0123456789abcdef.ics")
+            .body(Body::empty())
+            .unwrap();
+
+        let entry = AuditLogEntry::from_request(&request, None, None);
+
+        assert_eq!(entry.action, "GET /api/v1/calendar/{token}.ics");
+        assert_eq!(entry.entity_type, "calendar");
+        assert_eq!(entry.entity_id, Some("{token}.ics".to_string()));
+    }
+
     #[test]
     fn test_audit_log_entry_post_request() {
         let request = Request::builder()
@@ -317,6 +511,49 @@ mod tests {
         assert_eq!(entry.entity_id, Some("456".to_string()));
     }
 
+    #[test]
+    fn test_audit_log_entry_picks_up_request_id() {
+        let ctx = RequestContext::new(None, None);
+        let mut request = Request::builder()
+            .method(Method::GET)
+            .uri("/api/v1/patients")
+            .body(Body::empty())
+            .unwrap();
+        request.extensions_mut().insert(ctx.clone());
+
+        let entry = AuditLogEntry::from_request(&request, None, None);
+        assert_eq!(entry.request_id, Some(ctx.request_id));
+
+        // Without a request context there is no correlation ID
+        let request = Request::builder()
+            .uri("/api/v1/patients")
+            .body(Body::empty())
+            .unwrap();
+        let entry = AuditLogEntry::from_request(&request, None, None);
+        assert_eq!(entry.request_id, None);
+    }
+
+    #[test]
+    fn test_redact_path_hides_calendar_feed_token() {
+        assert_eq!(
+            redact_path("/api/v1/calendar/abc123.ics"),
+            "/api/v1/calendar/{token}.ics"
+        );
+        assert_eq!(
+            redact_path("/api/v2/calendar/abc123.ics"),
+            "/api/v2/calendar/{token}.ics"
+        );
+        assert_eq!(redact_path("/api/v1/calendar-feed"), "/api/v1/calendar-feed");
+    }
+
+    #[test]
+    fn test_security_event_kind() {
+        assert_eq!(security_event_kind(401), Some(SecurityEventKind::AuthFailure));
+        assert_eq!(security_event_kind(403), Some(SecurityEventKind::PermissionDenied));
+        assert_eq!(security_event_kind(200), None);
+        assert_eq!(security_event_kind(404), None);
+    }
+
     #[test]
     fn test_ip_address_parsing() {
         let ip_v4: std::net::IpAddr = "192.168.1.1".parse().unwrap();
@@ -2,19 +2,30 @@
  * JWT Authentication Middleware
  *
  * Validates JWT tokens and adds user information to request extensions.
+ * Also runs the request in an RLS scope for the user (`db::rls`), so its
+ * services share one transaction with the RLS context set once, committed
+ * unless the response is a server error. The impersonating admin goes in the
+ * impersonation task-local, so audit rows written while handling the request
+ * record both users.
  */
 
 use axum::{
     body::Body,
-    extract::State,
-    http::{Request, StatusCode},
+    extract::{OriginalUri, State},
+    http::{HeaderName, HeaderValue, Request, StatusCode},
     middleware::Next,
     response::{IntoResponse, Response},
 };
 use uuid::Uuid;
 
 use crate::{
+    db::rls::with_rls_scope,
     handlers::auth::AppState,
+    middleware::token_denylist::expiry_from_claim,
+    models::impersonation::{
+        with_impersonator_scope, Impersonation, IMPERSONATED_BY_HEADER,
+        IMPERSONATION_EXPIRES_HEADER,
+    },
     models::{AuthUser, UserRole},
 };
 
@@ -51,6 +62,11 @@ pub async fn jwt_auth_middleware(
         }
     };
 
+    // Reject tokens revoked via POST /auth/revoke
+    if state.token_denylist.is_revoked(&claims.jti) {
+        return Err((StatusCode::UNAUTHORIZED, "Token has been revoked"));
+    }
+
     // Parse user_id from claims
     let user_id = Uuid::parse_str(&claims.sub).map_err(|_| {
         (
@@ -63,6 +79,8 @@ pub async fn jwt_auth_middleware(
     let role = match claims.role.as_str() {
         "ADMIN" => UserRole::Admin,
         "DOCTOR" => UserRole::Doctor,
+        "NURSE" => UserRole::Nurse,
+        "RECEPTIONIST" => UserRole::Receptionist,
         _ => {
             return Err((
                 StatusCode::INTERNAL_SERVER_ERROR,
@@ -71,13 +89,43 @@ pub async fn jwt_auth_middleware(
         }
     };
 
+    // Impersonation tokens carry the admin in the `act` claim
+    let impersonation = match (&claims.act, claims.impersonator_id()) {
+        (None, _) => None,
+        (Some(_), Some(impersonator_id)) => Some(Impersonation {
+            impersonator_id,
+            jti: claims.jti.clone(),
+            expires_at: expiry_from_claim(claims.exp),
+        }),
+        (Some(_), None) => {
+            return Err((StatusCode::UNAUTHORIZED, "Invalid actor in token"));
+        }
+    };
+
+    // Impersonation rides on the admin's session, so the admin logging out
+    // (or timing out) also ends it
+    let session_user_id = impersonation
+        .as_ref()
+        .map_or(user_id, |imp| imp.impersonator_id);
+
     // Verify the user has an active session (AUTH-VULN-04: token revocation on logout)
-    if !state.session_manager.is_session_active(&user_id) {
+    if !state.session_manager.is_session_active(&session_user_id) {
         return Err((StatusCode::UNAUTHORIZED, "Session expired or invalidated"));
     }
 
+    // Account security settings cannot be changed while impersonating
+    if impersonation.is_some() {
+        let path = req
+            .extensions()
+            .get::<OriginalUri>()
+            .map_or_else(|| req.uri().path().to_string(), |uri| uri.path().to_string());
+        if is_blocked_while_impersonating(&path) {
+            return Err((StatusCode::FORBIDDEN, "Not allowed while impersonating"));
+        }
+    }
+
     // Refresh session activity timestamp to prevent inactivity timeout
-    state.session_manager.track_activity(&user_id);
+    state.session_manager.track_activity(&session_user_id);
 
     // Create AuthUser and add to request extensions
     let auth_user = AuthUser { user_id, role: role.clone() };
@@ -87,7 +135,52 @@ pub async fn jwt_auth_middleware(
     req.extensions_mut().insert(user_id);
     req.extensions_mut().insert(role);
 
-    Ok(next.run(req).await)
+    let impersonator_id = impersonation.as_ref().map(|imp| imp.impersonator_id);
+    if let Some(impersonation) = &impersonation {
+        req.extensions_mut().insert(impersonation.clone());
+    }
+
+    // Services share the request's RLS transaction, kept unless the request fails
+    let mut response = with_rls_scope(
+        user_id,
+        with_impersonator_scope(impersonator_id, next.run(req)),
+        |response: &Response| !response.status().is_server_error(),
+    )
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to complete request transaction: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to complete request")
+    })?;
+
+    let Some(impersonation) = impersonation else {
+        return Ok(response);
+    };
+
+    // Banner flag for the frontend
+    let headers = response.headers_mut();
+    if let Ok(value) = HeaderValue::from_str(&impersonation.impersonator_id.to_string()) {
+        headers.insert(HeaderName::from_static(IMPERSONATED_BY_HEADER), value);
+    }
+    if let Ok(value) = HeaderValue::from_str(&impersonation.expires_at.to_rfc3339()) {
+        headers.insert(HeaderName::from_static(IMPERSONATION_EXPIRES_HEADER), value);
+    }
+
+    Ok(response)
+}
+
+/// Routes an impersonating admin may not use (the user's own credentials,
+/// MFA and trusted devices)
+const IMPERSONATION_BLOCKED_PATHS: &[&str] = &[
+    "/api/v1/auth/change-password",
+    "/api/v1/auth/mfa",
+    "/api/v1/auth/trusted-devices",
+];
+
+/// Whether `path` is off limits during impersonation
+fn is_blocked_while_impersonating(path: &str) -> bool {
+    IMPERSONATION_BLOCKED_PATHS
+        .iter()
+        .any(|blocked| path == *blocked || path.starts_with(&format!("{}/", blocked)))
 }
 
 #[cfg(test)]
@@ -121,6 +214,7 @@ mod tests {
             refresh_secret: "test_refresh_secret_key_minimum_32_characters_long".to_string(),
             access_token_expiry: 1800,
             refresh_token_expiry: 604800,
+            key_ring: None,
         };
 
         let security_config = SecurityConfig {
@@ -128,6 +222,7 @@ mod tests {
             session_timeout: 1800,
             max_failed_login_attempts: 5,
             lockout_duration: 900,
+            captcha: None,
         };
 
         AuthService::new(jwt_config, security_config)
@@ -164,9 +259,16 @@ mod tests {
             pool: pool.clone(),
             auth_service,
             session_manager,
+            token_denylist: crate::middleware::token_denylist::TokenDenylist::new(),
+            login_throttle: crate::middleware::rate_limit::LoginThrottle::new(),
+            rate_limiter: crate::middleware::rate_limit::RateLimitLayer::new(),
+            admin_ip_allowlist: crate::middleware::ip_allowlist::AdminIpAllowlist::default(),
             encryption_key: None, // Not needed for auth middleware test
             email_service: None,  // Not needed for auth middleware test
+            sistema_ts: None,
+            video_room_provider: None,
             settings_service: Arc::new(SettingsService::new(pool)),
+            reference_cache: crate::services::ReferenceCache::memory(),
             start_time: std::time::SystemTime::now(),
             environment: "test".to_string(),
             #[cfg(feature = "rbac")]
@@ -214,9 +316,16 @@ mod tests {
             pool: pool.clone(),
             auth_service,
             session_manager: crate::middleware::session_timeout::SessionManager::new(1800),
+            token_denylist: crate::middleware::token_denylist::TokenDenylist::new(),
+            login_throttle: crate::middleware::rate_limit::LoginThrottle::new(),
+            rate_limiter: crate::middleware::rate_limit::RateLimitLayer::new(),
+            admin_ip_allowlist: crate::middleware::ip_allowlist::AdminIpAllowlist::default(),
             encryption_key: None, // Not needed for auth middleware test
             email_service: None,  // Not needed for auth middleware test
+            sistema_ts: None,
+            video_room_provider: None,
             settings_service: Arc::new(SettingsService::new(pool)),
+            reference_cache: crate::services::ReferenceCache::memory(),
             start_time: std::time::SystemTime::now(),
             environment: "test".to_string(),
             #[cfg(feature = "rbac")]
@@ -262,9 +371,16 @@ mod tests {
             pool: pool.clone(),
             auth_service,
             session_manager: crate::middleware::session_timeout::SessionManager::new(1800),
+            token_denylist: crate::middleware::token_denylist::TokenDenylist::new(),
+            login_throttle: crate::middleware::rate_limit::LoginThrottle::new(),
+            rate_limiter: crate::middleware::rate_limit::RateLimitLayer::new(),
+            admin_ip_allowlist: crate::middleware::ip_allowlist::AdminIpAllowlist::default(),
             encryption_key: None, // Not needed for auth middleware test
             email_service: None,  // Not needed for auth middleware test
+            sistema_ts: None,
+            video_room_provider: None,
             settings_service: Arc::new(SettingsService::new(pool)),
+            reference_cache: crate::services::ReferenceCache::memory(),
             start_time: std::time::SystemTime::now(),
             environment: "test".to_string(),
             #[cfg(feature = "rbac")]
@@ -290,4 +406,84 @@ mod tests {
 
         assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
     }
+
+    #[tokio::test]
+    async fn test_jwt_auth_middleware_revoked_token() {
+        let auth_service = test_auth_service();
+        let user_id = Uuid::new_v4();
+
+        let tokens = auth_service
+            .generate_tokens(&user_id, &UserRole::Doctor)
+            .unwrap();
+        let claims = auth_service.validate_token(&tokens.access_token).unwrap();
+
+        let pool = sqlx::PgPool::connect("postgresql://localhost/test")
+            .await
+            .unwrap_or_else(|_| panic!("Test skipped - no database"));
+
+        #[cfg(feature = "rbac")]
+        let enforcer = {
+            use crate::middleware::authorization::CasbinEnforcer;
+            CasbinEnforcer::new("casbin/model.conf", "casbin/policy.csv")
+                .await
+                .expect("Failed to initialize Casbin enforcer for test")
+        };
+
+        let session_manager = crate::middleware::session_timeout::SessionManager::new(1800);
+        session_manager.track_activity(&user_id);
+
+        // Revoke the token before it is used
+        let token_denylist = crate::middleware::token_denylist::TokenDenylist::new();
+        token_denylist.insert(
+            &claims.jti,
+            crate::middleware::token_denylist::expiry_from_claim(claims.exp),
+        );
+
+        let app_state = AppState {
+            pool: pool.clone(),
+            auth_service,
+            session_manager,
+            token_denylist,
+            login_throttle: crate::middleware::rate_limit::LoginThrottle::new(),
+            rate_limiter: crate::middleware::rate_limit::RateLimitLayer::new(),
+            admin_ip_allowlist: crate::middleware::ip_allowlist::AdminIpAllowlist::default(),
+            encryption_key: None, // Not needed for auth middleware test
+            email_service: None,  // Not needed for auth middleware test
+            sistema_ts: None,
+            video_room_provider: None,
+            settings_service: Arc::new(SettingsService::new(pool)),
+            reference_cache: crate::services::ReferenceCache::memory(),
+            start_time: std::time::SystemTime::now(),
+            environment: "test".to_string(),
+            #[cfg(feature = "rbac")]
+            enforcer,
+        };
+
+        let app = Router::new()
+            .route("/test", get(test_handler))
+            .layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                jwt_auth_middleware,
+            ))
+            .with_state(app_state);
+
+        let request = Request::builder()
+            .uri("/test")
+            .header("authorization", format!("Bearer {}", tokens.access_token))
+            .body(Body::empty())
+            .unwrap();
+
+        let response = app.oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[test]
+    fn test_blocked_while_impersonating() {
+        assert!(is_blocked_while_impersonating("/api/v1/auth/change-password"));
+        assert!(is_blocked_while_impersonating("/api/v1/auth/mfa/setup"));
+        assert!(is_blocked_while_impersonating("/api/v1/auth/trusted-devices/123"));
+        assert!(!is_blocked_while_impersonating("/api/v1/auth/mfa-other"));
+        assert!(!is_blocked_while_impersonating("/api/v1/patients"));
+    }
 }
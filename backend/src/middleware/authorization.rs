@@ -2,6 +2,10 @@
 //!
 //! This module provides RBAC-based authorization using Casbin.
 //! It checks if a user has permission to perform an action on a resource.
+//!
+//! Casbin decisions are per role. Locum accounts are additionally limited to
+//! the window of their access delegations (see `delegation_window_middleware`);
+//! which rows they can reach inside the window is decided by RLS.
 
 use axum::{
     extract::{Request, State},
@@ -10,12 +14,18 @@ use axum::{
     response::Response,
     Json,
 };
-use casbin::{CoreApi, DefaultModel, Enforcer, FileAdapter};
+use casbin::{CoreApi, DefaultModel, Enforcer, FileAdapter, MgmtApi, RbacApi};
+use sqlx::PgPool;
 use serde_json::json;
+use std::collections::BTreeMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
+use uuid::Uuid;
 
+use crate::handlers::auth::AppState;
+use crate::middleware::casbin_adapter::PgCasbinAdapter;
 use crate::models::user::UserRole;
+use crate::services::DelegationService;
 
 /// Permission requirement for a route (used by require_permission middleware)
 #[allow(dead_code)]
@@ -36,64 +46,265 @@ impl RequirePermission {
     }
 }
 
+/// Casbin subject for a role (matches the role names in the policy)
+fn role_subject(role: &UserRole) -> &'static str {
+    match role {
+        UserRole::Admin => "ADMIN",
+        UserRole::Doctor => "DOCTOR",
+        UserRole::Nurse => "NURSE",
+        UserRole::Receptionist => "RECEPTIONIST",
+    }
+}
+
+/// Permissions ADMIN must keep after a reload
+///
+/// A reload that would drop any of these is rejected, so a bad policy cannot
+/// lock administrators out of user management.
+const ADMIN_LOCKOUT_GUARD: &[(&str, &str)] = &[
+    ("users", "read"),
+    ("users", "update"),
+    ("users", "assign_role"),
+];
+
+/// Where the model and policies are loaded from
+enum PolicySource {
+    File {
+        model_path: String,
+        policy_path: String,
+    },
+    Database {
+        model_path: String,
+        seed_policy_path: String,
+        pool: PgPool,
+    },
+}
+
+impl PolicySource {
+    /// Build a fresh enforcer from the model file and the policy store
+    async fn build(&self) -> Result<Enforcer, casbin::Error> {
+        match self {
+            Self::File {
+                model_path,
+                policy_path,
+            } => {
+                // Load model from file
+                let model = DefaultModel::from_file(model_path).await?;
+
+                // Create file adapter with owned string for 'static lifetime
+                let adapter = FileAdapter::new(policy_path.clone());
+
+                // Create enforcer with model and adapter
+                Enforcer::new(model, adapter).await
+            }
+            Self::Database {
+                model_path,
+                seed_policy_path,
+                pool,
+            } => {
+                let model = DefaultModel::from_file(model_path).await?;
+                let adapter = PgCasbinAdapter::new(pool.clone(), Some(seed_policy_path.clone()));
+                Enforcer::new(model, adapter).await
+            }
+        }
+    }
+}
+
+/// Why a reload was not applied (the previous policy stays active)
+#[derive(Debug)]
+pub enum PolicyReloadError {
+    /// Model or policies could not be loaded
+    Load(casbin::Error),
+    /// Loaded policy failed validation
+    Rejected(String),
+}
+
+impl std::fmt::Display for PolicyReloadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Load(e) => write!(f, "failed to load policy: {}", e),
+            Self::Rejected(reason) => write!(f, "policy rejected: {}", reason),
+        }
+    }
+}
+
+/// Result of a successful reload
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyReloadSummary {
+    pub policies: usize,
+    pub role_assignments: usize,
+}
+
+/// Check a freshly loaded enforcer before it replaces the active one
+fn validate_enforcer(enforcer: &Enforcer) -> Result<(), String> {
+    let policies = enforcer.get_policy();
+    for role in [
+        UserRole::Admin,
+        UserRole::Doctor,
+        UserRole::Nurse,
+        UserRole::Receptionist,
+    ] {
+        let subject = role_subject(&role);
+        let has_rules = policies
+            .iter()
+            .any(|rule| rule.first().map(String::as_str) == Some(subject));
+        if !has_rules {
+            return Err(format!("no permission rules for role {}", subject));
+        }
+    }
+
+    for (resource, action) in ADMIN_LOCKOUT_GUARD {
+        let allowed = enforcer
+            .enforce((role_subject(&UserRole::Admin), *resource, *action))
+            .map_err(|e| e.to_string())?;
+        if !allowed {
+            return Err(format!("ADMIN would lose {} on {}", action, resource));
+        }
+    }
+
+    Ok(())
+}
+
 /// Casbin enforcer wrapper for thread-safe access
 #[derive(Clone)]
 pub struct CasbinEnforcer {
     enforcer: Arc<RwLock<Enforcer>>,
+    source: Arc<PolicySource>,
 }
 
 impl CasbinEnforcer {
     /// Initialize Casbin enforcer from policy files
     pub async fn new(model_path: &str, policy_path: &str) -> Result<Self, casbin::Error> {
-        // Load model from file
-        let model = DefaultModel::from_file(model_path).await?;
+        Self::from_source(PolicySource::File {
+            model_path: model_path.to_string(),
+            policy_path: policy_path.to_string(),
+        })
+        .await
+    }
 
-        // Create file adapter with owned string for 'static lifetime
-        let adapter = FileAdapter::new(policy_path.to_string());
+    /// Initialize Casbin enforcer with policies stored in the database
+    ///
+    /// Policy changes made through the management API are persisted in
+    /// `casbin_rules`. `seed_policy_path` is imported when the table is empty.
+    pub async fn with_database(
+        model_path: &str,
+        seed_policy_path: &str,
+        pool: PgPool,
+    ) -> Result<Self, casbin::Error> {
+        Self::from_source(PolicySource::Database {
+            model_path: model_path.to_string(),
+            seed_policy_path: seed_policy_path.to_string(),
+            pool,
+        })
+        .await
+    }
 
-        // Create enforcer with model and adapter
-        let enforcer = Enforcer::new(model, adapter).await?;
+    async fn from_source(source: PolicySource) -> Result<Self, casbin::Error> {
+        let enforcer = source.build().await?;
 
         Ok(Self {
             enforcer: Arc::new(RwLock::new(enforcer)),
+            source: Arc::new(source),
         })
     }
 
     /// Check if a user has permission to perform an action on a resource
     pub async fn enforce(&self, role: &UserRole, resource: &str, action: &str) -> Result<bool, casbin::Error> {
-        let role_str = match role {
-            UserRole::Admin => "ADMIN",
-            UserRole::Doctor => "DOCTOR",
-        };
+        let role_str = role_subject(role);
 
         let enforcer = self.enforcer.read().await;
         enforcer.enforce((role_str, resource, action))
     }
 
-    // TODO: Implement these methods when needed for dynamic role assignment
-    // /// Add a role assignment for a user (for future use with user-specific policies)
-    // pub async fn add_role_for_user(&self, user: &str, role: &str) -> Result<bool, casbin::Error> {
-    //     let mut enforcer = self.enforcer.write().await;
-    //     enforcer.add_grouping_policy(vec![user.to_string(), role.to_string()]).await
-    // }
+    /// Effective permissions of a role, including inherited ones
+    ///
+    /// Returns resource -> sorted actions.
+    pub async fn effective_permissions(&self, role: &UserRole) -> BTreeMap<String, Vec<String>> {
+        let mut enforcer = self.enforcer.write().await;
+        let mut permissions: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+        for rule in enforcer.get_implicit_permissions_for_user(role_subject(role), None) {
+            if let [_, resource, action, ..] = rule.as_slice() {
+                permissions
+                    .entry(resource.clone())
+                    .or_default()
+                    .push(action.clone());
+            }
+        }
+        for actions in permissions.values_mut() {
+            actions.sort();
+            actions.dedup();
+        }
+
+        permissions
+    }
+
+    /// Permission rules as `[role, resource, action]`
+    pub async fn policies(&self) -> Vec<Vec<String>> {
+        let enforcer = self.enforcer.read().await;
+        enforcer.get_policy()
+    }
+
+    /// Role assignments as `[member, role]`
+    pub async fn role_assignments(&self) -> Vec<Vec<String>> {
+        let enforcer = self.enforcer.read().await;
+        enforcer.get_grouping_policy()
+    }
 
-    // /// Remove a role assignment for a user
-    // pub async fn remove_role_for_user(&self, user: &str, role: &str) -> Result<bool, casbin::Error> {
-    //     let mut enforcer = self.enforcer.write().await;
-    //     enforcer.remove_grouping_policy(vec![user.to_string(), role.to_string()]).await
-    // }
+    /// Grant `action` on `resource` to `role`; false if it already existed
+    pub async fn add_policy(&self, role: &str, resource: &str, action: &str) -> Result<bool, casbin::Error> {
+        let mut enforcer = self.enforcer.write().await;
+        enforcer
+            .add_policy(vec![role.to_string(), resource.to_string(), action.to_string()])
+            .await
+    }
+
+    /// Revoke `action` on `resource` from `role`; false if it did not exist
+    pub async fn remove_policy(&self, role: &str, resource: &str, action: &str) -> Result<bool, casbin::Error> {
+        let mut enforcer = self.enforcer.write().await;
+        enforcer
+            .remove_policy(vec![role.to_string(), resource.to_string(), action.to_string()])
+            .await
+    }
+
+    /// Make `member` inherit the permissions of `role`
+    pub async fn add_role_for_user(&self, member: &str, role: &str) -> Result<bool, casbin::Error> {
+        let mut enforcer = self.enforcer.write().await;
+        enforcer
+            .add_grouping_policy(vec![member.to_string(), role.to_string()])
+            .await
+    }
 
-    // /// Get all roles for a user
-    // pub async fn get_roles_for_user(&self, user: &str) -> Vec<String> {
-    //     let enforcer = self.enforcer.read().await;
-    //     enforcer.get_roles_for_user(user, None)
-    // }
+    /// Remove a role assignment
+    pub async fn remove_role_for_user(&self, member: &str, role: &str) -> Result<bool, casbin::Error> {
+        let mut enforcer = self.enforcer.write().await;
+        enforcer
+            .remove_grouping_policy(vec![member.to_string(), role.to_string()])
+            .await
+    }
 
-    /// Reload policies from file (useful for dynamic policy updates)
+    /// Get all roles directly assigned to a member
     #[allow(dead_code)]
-    pub async fn reload_policies(&self) -> Result<(), casbin::Error> {
+    pub async fn get_roles_for_user(&self, member: &str) -> Vec<String> {
         let mut enforcer = self.enforcer.write().await;
-        enforcer.load_policy().await
+        enforcer.get_roles_for_user(member, None)
+    }
+
+    /// Reload the model file and policies without restarting the server
+    ///
+    /// A new enforcer is built and validated off to the side; only if it passes
+    /// is it swapped in. Requests keep using the previous policy until then, and
+    /// a broken or lockout-inducing policy is rejected with the old one intact.
+    pub async fn reload(&self) -> Result<PolicyReloadSummary, PolicyReloadError> {
+        let candidate = self.source.build().await.map_err(PolicyReloadError::Load)?;
+        validate_enforcer(&candidate).map_err(PolicyReloadError::Rejected)?;
+
+        let summary = PolicyReloadSummary {
+            policies: candidate.get_policy().len(),
+            role_assignments: candidate.get_grouping_policy().len(),
+        };
+        *self.enforcer.write().await = candidate;
+
+        Ok(summary)
     }
 }
 
@@ -172,6 +383,54 @@ pub async fn require_permission(
     Ok(next.run(req).await)
 }
 
+/// Delegation window middleware
+///
+/// Rejects requests from locum accounts that have no delegation active right
+/// now, so access ends as soon as the last window closes or is revoked.
+/// Must run after `jwt_auth_middleware`. Other users pass through unchanged.
+pub async fn delegation_window_middleware(
+    State(state): State<AppState>,
+    req: Request,
+    next: Next,
+) -> Result<Response, (StatusCode, Json<serde_json::Value>)> {
+    let Some(user_id) = req.extensions().get::<Uuid>().copied() else {
+        return Ok(next.run(req).await);
+    };
+
+    let window = DelegationService::new(state.pool.clone())
+        .window(user_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to check delegation window for user {}: {}", user_id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "AUTHORIZATION_ERROR",
+                    "message": "Failed to check permissions"
+                })),
+            )
+        })?;
+
+    if let Some(window) = window {
+        if window.is_locum && !window.has_active_delegation {
+            tracing::warn!(
+                "Locum user {} rejected on {}: no active delegation",
+                user_id,
+                req.uri().path()
+            );
+            return Err((
+                StatusCode::FORBIDDEN,
+                Json(json!({
+                    "error": "DELEGATION_INACTIVE",
+                    "message": "No active access delegation for this account"
+                })),
+            ));
+        }
+    }
+
+    Ok(next.run(req).await)
+}
+
 /// Helper macro to create permission requirements
 #[macro_export]
 macro_rules! require_perm {
@@ -235,6 +494,150 @@ mod tests {
         assert!(!has_perm, "DOCTOR should NOT have read permission on audit_logs");
     }
 
+    #[tokio::test]
+    async fn test_nurse_permissions() {
+        let enforcer = CasbinEnforcer::new(
+            "casbin/model.conf",
+            "casbin/policy.csv",
+        ).await.unwrap();
+
+        let has_perm = enforcer.enforce(&UserRole::Nurse, "visits", "update").await.unwrap();
+        assert!(has_perm, "NURSE should be able to edit draft visits");
+
+        let has_perm = enforcer.enforce(&UserRole::Nurse, "visits", "sign").await.unwrap();
+        assert!(!has_perm, "NURSE should NOT be able to sign visits");
+
+        let has_perm = enforcer.enforce(&UserRole::Nurse, "prescriptions", "create").await.unwrap();
+        assert!(!has_perm, "NURSE should NOT be able to prescribe");
+
+        let has_perm = enforcer.enforce(&UserRole::Nurse, "patients", "update").await.unwrap();
+        assert!(!has_perm, "NURSE should have read-only patient access");
+    }
+
+    #[tokio::test]
+    async fn test_receptionist_permissions() {
+        let enforcer = CasbinEnforcer::new(
+            "casbin/model.conf",
+            "casbin/policy.csv",
+        ).await.unwrap();
+
+        let has_perm = enforcer.enforce(&UserRole::Receptionist, "appointments", "create").await.unwrap();
+        assert!(has_perm, "RECEPTIONIST should be able to schedule appointments");
+
+        let has_perm = enforcer.enforce(&UserRole::Receptionist, "patients", "update").await.unwrap();
+        assert!(has_perm, "RECEPTIONIST should be able to update demographics");
+
+        for resource in ["visits", "diagnoses", "prescriptions", "documents"] {
+            let has_perm = enforcer.enforce(&UserRole::Receptionist, resource, "read").await.unwrap();
+            assert!(!has_perm, "RECEPTIONIST should NOT read {}", resource);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_delegation_permissions() {
+        let enforcer = CasbinEnforcer::new(
+            "casbin/model.conf",
+            "casbin/policy.csv",
+        ).await.unwrap();
+
+        let has_perm = enforcer.enforce(&UserRole::Admin, "delegations", "create").await.unwrap();
+        assert!(has_perm, "ADMIN should be able to grant delegations");
+
+        let has_perm = enforcer.enforce(&UserRole::Doctor, "delegations", "read_own").await.unwrap();
+        assert!(has_perm, "DOCTOR should see their own delegations");
+
+        let has_perm = enforcer.enforce(&UserRole::Doctor, "delegations", "create").await.unwrap();
+        assert!(!has_perm, "DOCTOR should NOT be able to grant delegations");
+    }
+
+    #[tokio::test]
+    async fn test_effective_permissions() {
+        let enforcer = CasbinEnforcer::new(
+            "casbin/model.conf",
+            "casbin/policy.csv",
+        ).await.unwrap();
+
+        let permissions = enforcer.effective_permissions(&UserRole::Doctor).await;
+        let patient_actions = permissions.get("patients").expect("DOCTOR has patient permissions");
+        assert!(patient_actions.contains(&"read".to_string()));
+        assert!(!patient_actions.contains(&"delete".to_string()));
+        assert!(!permissions.contains_key("audit_logs"));
+
+        // Inherited permissions are included
+        enforcer.add_role_for_user("DOCTOR", "ADMIN").await.unwrap();
+        let permissions = enforcer.effective_permissions(&UserRole::Doctor).await;
+        assert!(permissions.contains_key("audit_logs"));
+    }
+
+    #[tokio::test]
+    async fn test_runtime_policy_changes() {
+        let enforcer = CasbinEnforcer::new(
+            "casbin/model.conf",
+            "casbin/policy.csv",
+        ).await.unwrap();
+
+        assert!(!enforcer.enforce(&UserRole::Doctor, "audit_logs", "read").await.unwrap());
+
+        assert!(enforcer.add_policy("DOCTOR", "audit_logs", "read").await.unwrap());
+        assert!(!enforcer.add_policy("DOCTOR", "audit_logs", "read").await.unwrap());
+        assert!(enforcer.enforce(&UserRole::Doctor, "audit_logs", "read").await.unwrap());
+        assert!(enforcer
+            .policies()
+            .await
+            .contains(&vec!["DOCTOR".to_string(), "audit_logs".to_string(), "read".to_string()]));
+
+        assert!(enforcer.remove_policy("DOCTOR", "audit_logs", "read").await.unwrap());
+        assert!(!enforcer.enforce(&UserRole::Doctor, "audit_logs", "read").await.unwrap());
+
+        // Role assignment: DOCTOR inherits ADMIN permissions
+        assert!(enforcer.add_role_for_user("DOCTOR", "ADMIN").await.unwrap());
+        assert!(enforcer.enforce(&UserRole::Doctor, "users", "delete").await.unwrap());
+        assert_eq!(enforcer.get_roles_for_user("DOCTOR").await, vec!["ADMIN".to_string()]);
+
+        assert!(enforcer.remove_role_for_user("DOCTOR", "ADMIN").await.unwrap());
+        assert!(!enforcer.enforce(&UserRole::Doctor, "users", "delete").await.unwrap());
+        assert!(enforcer.role_assignments().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_reload_swaps_valid_policy_and_rejects_invalid() {
+        let dir = std::env::temp_dir().join(format!("docpat-casbin-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let policy_path = dir.join("policy.csv");
+        let seed = std::fs::read_to_string("casbin/policy.csv").unwrap();
+        std::fs::write(&policy_path, &seed).unwrap();
+
+        let enforcer = CasbinEnforcer::new("casbin/model.conf", policy_path.to_str().unwrap())
+            .await
+            .unwrap();
+        assert!(!enforcer.enforce(&UserRole::Doctor, "audit_logs", "read").await.unwrap());
+
+        // Valid change is picked up
+        std::fs::write(&policy_path, format!("{}\np, DOCTOR, audit_logs, read\n", seed)).unwrap();
+        let summary = enforcer.reload().await.unwrap();
+        assert!(summary.policies > 0);
+        assert!(enforcer.enforce(&UserRole::Doctor, "audit_logs", "read").await.unwrap());
+
+        // Policy that would lock ADMIN out of user management is rejected
+        let without_admin_users: String = seed
+            .lines()
+            .filter(|line| !line.starts_with("p, ADMIN, users"))
+            .map(|line| format!("{}\n", line))
+            .collect();
+        std::fs::write(&policy_path, without_admin_users).unwrap();
+        assert!(matches!(enforcer.reload().await, Err(PolicyReloadError::Rejected(_))));
+
+        // Unreadable policy is rejected
+        std::fs::remove_file(&policy_path).unwrap();
+        assert!(matches!(enforcer.reload().await, Err(PolicyReloadError::Load(_))));
+
+        // Previous policy is still active
+        assert!(enforcer.enforce(&UserRole::Doctor, "audit_logs", "read").await.unwrap());
+        assert!(enforcer.enforce(&UserRole::Admin, "users", "update").await.unwrap());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
     #[tokio::test]
     async fn test_permission_requirements() {
         let perm = RequirePermission::new("patients", "create");
@@ -0,0 +1,373 @@
+//! Casbin Policy Adapter (PostgreSQL via sqlx)
+//!
+//! Persists Casbin policy rules in the `casbin_rules` table so permissions can
+//! be changed at runtime (see `handlers::authorization`) without editing
+//! `casbin/policy.csv` and redeploying.
+//!
+//! `casbin/policy.csv` remains the seed: when the table is empty on first
+//! load, its rules are imported. After that the table is the source of truth.
+
+use async_trait::async_trait;
+use casbin::{error::AdapterError, Adapter, Error as CasbinError, Filter, Model};
+use sqlx::PgPool;
+
+/// Number of rule value columns (v0..v5)
+const RULE_FIELDS: usize = 6;
+
+/// Rule as stored in `casbin_rules`
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
+struct CasbinRuleRow {
+    ptype: String,
+    v0: String,
+    v1: String,
+    v2: String,
+    v3: String,
+    v4: String,
+    v5: String,
+}
+
+impl CasbinRuleRow {
+    /// Build a row from a policy type and rule values
+    fn new(ptype: &str, rule: &[String]) -> Self {
+        let value = |i: usize| rule.get(i).cloned().unwrap_or_default();
+        Self {
+            ptype: ptype.to_string(),
+            v0: value(0),
+            v1: value(1),
+            v2: value(2),
+            v3: value(3),
+            v4: value(4),
+            v5: value(5),
+        }
+    }
+
+    /// Rule values without trailing empty fields
+    fn rule(&self) -> Vec<String> {
+        let mut rule = vec![
+            self.v0.clone(),
+            self.v1.clone(),
+            self.v2.clone(),
+            self.v3.clone(),
+            self.v4.clone(),
+            self.v5.clone(),
+        ];
+        while rule.last().is_some_and(|v| v.is_empty()) {
+            rule.pop();
+        }
+        rule
+    }
+
+    /// Policy section ("p" or "g") derived from the policy type
+    fn section(&self) -> &str {
+        &self.ptype[..1]
+    }
+}
+
+/// Parse one `policy.csv` line (`p, DOCTOR, patients, read`)
+///
+/// Returns None for blank lines and comments.
+fn parse_policy_line(line: &str) -> Option<(String, Vec<String>)> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    let mut fields = line.split(',').map(|field| field.trim().to_string());
+    let ptype = fields.next().filter(|ptype| !ptype.is_empty())?;
+    let rule: Vec<String> = fields.collect();
+    if rule.is_empty() || rule.len() > RULE_FIELDS {
+        return None;
+    }
+
+    Some((ptype, rule))
+}
+
+/// Whether a rule matches filter values (empty filter values match anything)
+fn matches_filter(rule: &[String], field_index: usize, values: &[&str]) -> bool {
+    values.iter().enumerate().all(|(i, value)| {
+        value.is_empty() || rule.get(field_index + i).map(String::as_str) == Some(*value)
+    })
+}
+
+fn adapter_error(e: sqlx::Error) -> CasbinError {
+    CasbinError::from(AdapterError(Box::new(e)))
+}
+
+/// Casbin adapter backed by the `casbin_rules` table
+pub struct PgCasbinAdapter {
+    pool: PgPool,
+    /// Policy file imported when the table is empty
+    seed_path: Option<String>,
+    is_filtered: bool,
+}
+
+impl PgCasbinAdapter {
+    /// Create an adapter, seeding an empty table from `seed_path` on first load
+    pub fn new(pool: PgPool, seed_path: Option<String>) -> Self {
+        Self {
+            pool,
+            seed_path,
+            is_filtered: false,
+        }
+    }
+
+    /// Import the seed policy file if the table is empty
+    async fn seed_if_empty(&self) -> casbin::Result<()> {
+        let Some(path) = &self.seed_path else {
+            return Ok(());
+        };
+
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM casbin_rules")
+            .fetch_one(&self.pool)
+            .await
+            .map_err(adapter_error)?;
+        if count > 0 {
+            return Ok(());
+        }
+
+        let contents = tokio::fs::read_to_string(path)
+            .await
+            .map_err(|e| CasbinError::from(AdapterError(Box::new(e))))?;
+        let rows: Vec<CasbinRuleRow> = contents
+            .lines()
+            .filter_map(parse_policy_line)
+            .map(|(ptype, rule)| CasbinRuleRow::new(&ptype, &rule))
+            .collect();
+
+        tracing::info!("Seeding {} Casbin rules from {}", rows.len(), path);
+        self.insert_rows(&rows).await?;
+        Ok(())
+    }
+
+    async fn load_rows(&self) -> casbin::Result<Vec<CasbinRuleRow>> {
+        sqlx::query_as::<_, CasbinRuleRow>(
+            "SELECT ptype, v0, v1, v2, v3, v4, v5 FROM casbin_rules ORDER BY id",
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(adapter_error)
+    }
+
+    /// Insert rows, skipping duplicates; returns whether anything was inserted
+    async fn insert_rows(&self, rows: &[CasbinRuleRow]) -> casbin::Result<bool> {
+        let mut tx = self.pool.begin().await.map_err(adapter_error)?;
+        let mut inserted = false;
+
+        for row in rows {
+            let result = sqlx::query(
+                r#"
+                INSERT INTO casbin_rules (ptype, v0, v1, v2, v3, v4, v5)
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+                ON CONFLICT (ptype, v0, v1, v2, v3, v4, v5) DO NOTHING
+                "#,
+            )
+            .bind(&row.ptype)
+            .bind(&row.v0)
+            .bind(&row.v1)
+            .bind(&row.v2)
+            .bind(&row.v3)
+            .bind(&row.v4)
+            .bind(&row.v5)
+            .execute(&mut *tx)
+            .await
+            .map_err(adapter_error)?;
+            inserted |= result.rows_affected() > 0;
+        }
+
+        tx.commit().await.map_err(adapter_error)?;
+        Ok(inserted)
+    }
+
+    /// Delete rows; returns whether anything was deleted
+    async fn delete_rows(&self, rows: &[CasbinRuleRow]) -> casbin::Result<bool> {
+        let mut tx = self.pool.begin().await.map_err(adapter_error)?;
+        let mut deleted = false;
+
+        for row in rows {
+            let result = sqlx::query(
+                r#"
+                DELETE FROM casbin_rules
+                WHERE ptype = $1 AND v0 = $2 AND v1 = $3 AND v2 = $4
+                  AND v3 = $5 AND v4 = $6 AND v5 = $7
+                "#,
+            )
+            .bind(&row.ptype)
+            .bind(&row.v0)
+            .bind(&row.v1)
+            .bind(&row.v2)
+            .bind(&row.v3)
+            .bind(&row.v4)
+            .bind(&row.v5)
+            .execute(&mut *tx)
+            .await
+            .map_err(adapter_error)?;
+            deleted |= result.rows_affected() > 0;
+        }
+
+        tx.commit().await.map_err(adapter_error)?;
+        Ok(deleted)
+    }
+}
+
+#[async_trait]
+impl Adapter for PgCasbinAdapter {
+    async fn load_policy(&mut self, m: &mut dyn Model) -> casbin::Result<()> {
+        self.seed_if_empty().await?;
+
+        for row in self.load_rows().await? {
+            m.add_policy(row.section(), &row.ptype, row.rule());
+        }
+        self.is_filtered = false;
+
+        Ok(())
+    }
+
+    async fn load_filtered_policy<'a>(
+        &mut self,
+        m: &mut dyn Model,
+        f: Filter<'a>,
+    ) -> casbin::Result<()> {
+        self.seed_if_empty().await?;
+
+        for row in self.load_rows().await? {
+            let rule = row.rule();
+            let values = if row.section() == "g" { &f.g } else { &f.p };
+            if matches_filter(&rule, 0, values) {
+                m.add_policy(row.section(), &row.ptype, rule);
+            }
+        }
+        self.is_filtered = true;
+
+        Ok(())
+    }
+
+    async fn save_policy(&mut self, m: &mut dyn Model) -> casbin::Result<()> {
+        let mut rows = Vec::new();
+        for sec in ["p", "g"] {
+            let Some(assertions) = m.get_model().get(sec) else {
+                continue;
+            };
+            for ptype in assertions.keys() {
+                rows.extend(
+                    m.get_policy(sec, ptype)
+                        .iter()
+                        .map(|rule| CasbinRuleRow::new(ptype, rule)),
+                );
+            }
+        }
+
+        sqlx::query("DELETE FROM casbin_rules")
+            .execute(&self.pool)
+            .await
+            .map_err(adapter_error)?;
+        self.insert_rows(&rows).await?;
+
+        Ok(())
+    }
+
+    async fn clear_policy(&mut self) -> casbin::Result<()> {
+        sqlx::query("DELETE FROM casbin_rules")
+            .execute(&self.pool)
+            .await
+            .map_err(adapter_error)?;
+        Ok(())
+    }
+
+    fn is_filtered(&self) -> bool {
+        self.is_filtered
+    }
+
+    async fn add_policy(&mut self, _sec: &str, ptype: &str, rule: Vec<String>) -> casbin::Result<bool> {
+        self.insert_rows(&[CasbinRuleRow::new(ptype, &rule)]).await
+    }
+
+    async fn add_policies(
+        &mut self,
+        _sec: &str,
+        ptype: &str,
+        rules: Vec<Vec<String>>,
+    ) -> casbin::Result<bool> {
+        let rows: Vec<CasbinRuleRow> = rules
+            .iter()
+            .map(|rule| CasbinRuleRow::new(ptype, rule))
+            .collect();
+        self.insert_rows(&rows).await
+    }
+
+    async fn remove_policy(&mut self, _sec: &str, ptype: &str, rule: Vec<String>) -> casbin::Result<bool> {
+        self.delete_rows(&[CasbinRuleRow::new(ptype, &rule)]).await
+    }
+
+    async fn remove_policies(
+        &mut self,
+        _sec: &str,
+        ptype: &str,
+        rules: Vec<Vec<String>>,
+    ) -> casbin::Result<bool> {
+        let rows: Vec<CasbinRuleRow> = rules
+            .iter()
+            .map(|rule| CasbinRuleRow::new(ptype, rule))
+            .collect();
+        self.delete_rows(&rows).await
+    }
+
+    async fn remove_filtered_policy(
+        &mut self,
+        _sec: &str,
+        ptype: &str,
+        field_index: usize,
+        field_values: Vec<String>,
+    ) -> casbin::Result<bool> {
+        let values: Vec<&str> = field_values.iter().map(String::as_str).collect();
+        let rows: Vec<CasbinRuleRow> = self
+            .load_rows()
+            .await?
+            .into_iter()
+            .filter(|row| row.ptype == ptype && matches_filter(&row.rule(), field_index, &values))
+            .collect();
+        self.delete_rows(&rows).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_policy_line() {
+        assert_eq!(
+            parse_policy_line("p, DOCTOR, patients, read"),
+            Some((
+                "p".to_string(),
+                vec!["DOCTOR".to_string(), "patients".to_string(), "read".to_string()]
+            ))
+        );
+        assert_eq!(
+            parse_policy_line("  g, SENIOR_DOCTOR, DOCTOR  "),
+            Some(("g".to_string(), vec!["SENIOR_DOCTOR".to_string(), "DOCTOR".to_string()]))
+        );
+        assert_eq!(parse_policy_line("# p, ADMIN, users, read"), None);
+        assert_eq!(parse_policy_line("   "), None);
+        assert_eq!(parse_policy_line("p"), None);
+    }
+
+    #[test]
+    fn test_rule_roundtrip_trims_empty_fields() {
+        let rule = vec!["ADMIN".to_string(), "users".to_string(), "read".to_string()];
+        let row = CasbinRuleRow::new("p", &rule);
+        assert_eq!(row.v3, "");
+        assert_eq!(row.rule(), rule);
+        assert_eq!(row.section(), "p");
+        assert_eq!(CasbinRuleRow::new("g2", &rule).section(), "g");
+    }
+
+    #[test]
+    fn test_matches_filter() {
+        let rule = vec!["DOCTOR".to_string(), "patients".to_string(), "read".to_string()];
+        assert!(matches_filter(&rule, 0, &["DOCTOR"]));
+        assert!(matches_filter(&rule, 0, &["", "patients"]));
+        assert!(matches_filter(&rule, 1, &["patients", "read"]));
+        assert!(!matches_filter(&rule, 0, &["ADMIN"]));
+        assert!(!matches_filter(&rule, 2, &["read", "extra"]));
+    }
+}
@@ -0,0 +1,174 @@
+/*!
+ * Client IP Resolution
+ *
+ * Determines the address of the client behind the reverse proxy. The
+ * connection peer is authoritative; `X-Forwarded-For` is only followed while
+ * the current hop is a trusted proxy (`TRUSTED_PROXIES`), walking the header
+ * from right to left. Entries a client prepends itself are never reached, as
+ * nginx (`$proxy_add_x_forwarded_for`) appends the real peer to the right.
+ *
+ * `X-Real-IP` is honoured only from a trusted peer that sent no
+ * `X-Forwarded-For`. With no trusted proxies configured the peer address is
+ * used as-is.
+ */
+
+use axum::{extract::ConnectInfo, http::HeaderMap};
+use ipnetwork::IpNetwork;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// Proxies whose forwarding headers are trusted (set once at startup)
+static TRUSTED_PROXIES: OnceLock<Vec<IpNetwork>> = OnceLock::new();
+
+/// Register the trusted reverse proxies; call once at startup
+pub fn init_trusted_proxies(proxies: Vec<IpNetwork>) {
+    if TRUSTED_PROXIES.set(proxies).is_err() {
+        tracing::warn!("Trusted proxies already initialized");
+    }
+}
+
+/// Trusted reverse proxies (empty until initialized)
+pub fn trusted_proxies() -> &'static [IpNetwork] {
+    TRUSTED_PROXIES.get().map(Vec::as_slice).unwrap_or(&[])
+}
+
+/// Resolve the client IP from the connection peer and forwarding headers
+///
+/// Returns the rightmost address that is not a trusted proxy. When every hop
+/// is trusted the leftmost one is returned.
+pub fn resolve_client_ip(
+    peer: Option<IpAddr>,
+    forwarded_for: Option<&str>,
+    real_ip: Option<&str>,
+    trusted: &[IpNetwork],
+) -> Option<IpAddr> {
+    let is_trusted = |ip: IpAddr| trusted.iter().any(|range| range.contains(ip));
+
+    let mut client = peer?;
+    if !is_trusted(client) {
+        return Some(client);
+    }
+
+    let Some(forwarded_for) = forwarded_for else {
+        return Some(
+            real_ip
+                .and_then(|ip| IpAddr::from_str(ip.trim()).ok())
+                .unwrap_or(client),
+        );
+    };
+
+    for hop in forwarded_for.rsplit(',') {
+        // A malformed hop ends the chain: nothing left of it can be trusted
+        let Ok(hop) = IpAddr::from_str(hop.trim()) else {
+            break;
+        };
+        client = hop;
+        if !is_trusted(hop) {
+            break;
+        }
+    }
+
+    Some(client)
+}
+
+/// Resolve the client IP of a request against the trusted proxies
+pub fn client_ip(
+    headers: &HeaderMap,
+    connect_info: Option<&ConnectInfo<SocketAddr>>,
+) -> Option<IpAddr> {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+
+    resolve_client_ip(
+        connect_info.map(|ConnectInfo(addr)| addr.ip()),
+        header("x-forwarded-for"),
+        header("x-real-ip"),
+        trusted_proxies(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    fn proxies() -> Vec<IpNetwork> {
+        vec![
+            "172.16.0.0/12".parse().unwrap(),
+            "127.0.0.1".parse().unwrap(),
+        ]
+    }
+
+    #[test]
+    fn test_untrusted_peer_ignores_headers() {
+        let resolved = resolve_client_ip(
+            Some(ip("203.0.113.7")),
+            Some("10.0.0.1"),
+            Some("10.0.0.2"),
+            &proxies(),
+        );
+        assert_eq!(resolved, Some(ip("203.0.113.7")));
+    }
+
+    #[test]
+    fn test_no_trusted_proxies_uses_peer() {
+        let resolved = resolve_client_ip(Some(ip("172.18.0.5")), Some("203.0.113.7"), None, &[]);
+        assert_eq!(resolved, Some(ip("172.18.0.5")));
+    }
+
+    #[test]
+    fn test_spoofed_forwarded_for_is_skipped() {
+        // Client sent "X-Forwarded-For: 10.0.0.1"; nginx appended the real peer
+        let resolved = resolve_client_ip(
+            Some(ip("172.18.0.5")),
+            Some("10.0.0.1, 203.0.113.7"),
+            Some("203.0.113.7"),
+            &proxies(),
+        );
+        assert_eq!(resolved, Some(ip("203.0.113.7")));
+    }
+
+    #[test]
+    fn test_chain_of_trusted_proxies() {
+        let resolved = resolve_client_ip(
+            Some(ip("127.0.0.1")),
+            Some("198.51.100.4, 172.20.0.2"),
+            None,
+            &proxies(),
+        );
+        assert_eq!(resolved, Some(ip("198.51.100.4")));
+    }
+
+    #[test]
+    fn test_malformed_hop_stops_the_walk() {
+        let resolved = resolve_client_ip(
+            Some(ip("172.18.0.5")),
+            Some("198.51.100.4, garbage, 172.20.0.2"),
+            None,
+            &proxies(),
+        );
+        assert_eq!(resolved, Some(ip("172.20.0.2")));
+    }
+
+    #[test]
+    fn test_real_ip_only_without_forwarded_for() {
+        let resolved = resolve_client_ip(
+            Some(ip("172.18.0.5")),
+            None,
+            Some("198.51.100.4"),
+            &proxies(),
+        );
+        assert_eq!(resolved, Some(ip("198.51.100.4")));
+    }
+
+    #[test]
+    fn test_no_peer_resolves_nothing() {
+        assert_eq!(
+            resolve_client_ip(None, Some("198.51.100.4"), None, &proxies()),
+            None
+        );
+    }
+}
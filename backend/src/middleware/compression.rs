@@ -0,0 +1,119 @@
+/*!
+ * Response Compression
+ *
+ * Compresses responses with brotli or gzip, whichever the client prefers
+ * (`Accept-Encoding`). Report and list JSON shrinks several times over.
+ *
+ * Left uncompressed:
+ * - bodies under `MIN_COMPRESSED_SIZE` (headers would outweigh the gain)
+ * - content that is compressed already or gains nothing: PDF, ZIP and
+ *   Excel downloads, images, audio, video and opaque binary files
+ * - server-sent events, which must reach the client as they are written
+ */
+
+use axum::{
+    body::HttpBody,
+    http::{header::CONTENT_TYPE, Response},
+};
+use tower_http::compression::{
+    predicate::{NotForContentType, Predicate, SizeAbove},
+    CompressionLayer,
+};
+
+/// Smallest body compressed, in bytes
+pub const MIN_COMPRESSED_SIZE: u16 = 1024;
+
+/// Content types never compressed (matched by prefix)
+const PRECOMPRESSED_CONTENT_TYPES: &[&str] = &[
+    "application/pdf",
+    "application/zip",
+    "application/gzip",
+    "application/octet-stream",
+    // xlsx and the other Office Open XML formats are ZIP archives
+    "application/vnd.openxmlformats-officedocument",
+    "audio/",
+    "video/",
+];
+
+/// Skips the content types in `PRECOMPRESSED_CONTENT_TYPES`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NotPrecompressed;
+
+impl Predicate for NotPrecompressed {
+    fn should_compress<B>(&self, response: &Response<B>) -> bool
+    where
+        B: HttpBody,
+    {
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+
+        !PRECOMPRESSED_CONTENT_TYPES
+            .iter()
+            .any(|prefix| content_type.starts_with(prefix))
+    }
+}
+
+/// When a response is worth compressing
+pub fn compression_predicate() -> impl Predicate {
+    SizeAbove::new(MIN_COMPRESSED_SIZE)
+        .and(NotForContentType::GRPC)
+        .and(NotForContentType::IMAGES)
+        .and(NotForContentType::SSE)
+        .and(NotPrecompressed)
+}
+
+/// Compression layer for the whole application (brotli and gzip)
+pub fn compression_layer() -> CompressionLayer<impl Predicate> {
+    CompressionLayer::new()
+        .no_deflate()
+        .no_zstd()
+        .compress_when(compression_predicate())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+
+    fn response(content_type: &str, size: usize) -> Response<Body> {
+        Response::builder()
+            .header(CONTENT_TYPE, content_type)
+            .body(Body::from(vec![b'a'; size]))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_compresses_large_json() {
+        let predicate = compression_predicate();
+        assert!(predicate.should_compress(&response("application/json", 64 * 1024)));
+        assert!(predicate.should_compress(&response("text/csv; charset=utf-8", 4096)));
+    }
+
+    #[test]
+    fn test_skips_small_bodies() {
+        let predicate = compression_predicate();
+        assert!(!predicate.should_compress(&response("application/json", 200)));
+    }
+
+    #[test]
+    fn test_skips_precompressed_content() {
+        let predicate = compression_predicate();
+        for content_type in [
+            "application/pdf",
+            "application/zip",
+            "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            "image/jpeg",
+            "audio/webm",
+            "text/event-stream",
+        ] {
+            assert!(
+                !predicate.should_compress(&response(content_type, 64 * 1024)),
+                "{}",
+                content_type
+            );
+        }
+    }
+}
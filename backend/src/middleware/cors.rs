@@ -107,6 +107,8 @@ impl CorsConfig {
                 HeaderName::from_static("x-ratelimit-limit"),
                 HeaderName::from_static("x-ratelimit-remaining"),
                 HeaderName::from_static("x-ratelimit-reset"),
+                HeaderName::from_static("x-impersonated-by"),
+                HeaderName::from_static("x-impersonation-expires"),
             ])
             // Set max age for preflight cache
             .max_age(self.max_age);
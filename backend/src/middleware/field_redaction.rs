@@ -0,0 +1,226 @@
+/*!
+ * Field Redaction Middleware
+ *
+ * Shapes JSON responses per role: sensitive fields are omitted or masked for
+ * roles that should not see them. What is hidden from whom is declared once in
+ * `FIELD_POLICY`; handlers and DTOs stay role-agnostic.
+ *
+ * The middleware runs after `jwt_auth_middleware` (it needs the `UserRole`
+ * extension) and is layered per route group with the resources whose rules
+ * apply there. Rules match object keys at any depth, so lists, paginated
+ * wrappers and nested records are covered.
+ *
+ * Casbin decides whether a role may call an endpoint at all; this decides
+ * which fields of an allowed response it gets to see.
+ */
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use serde_json::Value;
+
+use crate::models::user::UserRole;
+
+/// What happens to a field hidden from a role
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Redaction {
+    /// Remove the field from the response
+    Omit,
+    /// Keep the field but mask string values (`RSS************U`)
+    Mask,
+}
+
+/// One entry of the field policy
+#[derive(Debug)]
+pub struct FieldRule {
+    /// Resource the rule belongs to (matches the Casbin resource name)
+    pub resource: &'static str,
+    pub field: &'static str,
+    /// Roles the field is hidden from
+    pub roles: &'static [UserRole],
+    pub redaction: Redaction,
+}
+
+/// Field policy
+///
+/// RECEPTIONIST works with demographics only, so patient clinical fields are
+/// omitted. NURSE sees clinical data needed for care, but not the fiscal code
+/// (masked) or the physician's free-text clinical notes.
+pub const FIELD_POLICY: &[FieldRule] = &[
+    // Patients
+    FieldRule { resource: "patients", field: "fiscal_code", roles: &[UserRole::Nurse], redaction: Redaction::Mask },
+    FieldRule { resource: "patients", field: "blood_type", roles: &[UserRole::Receptionist], redaction: Redaction::Omit },
+    FieldRule { resource: "patients", field: "allergies", roles: &[UserRole::Receptionist], redaction: Redaction::Omit },
+    FieldRule { resource: "patients", field: "chronic_conditions", roles: &[UserRole::Receptionist], redaction: Redaction::Omit },
+    FieldRule { resource: "patients", field: "current_medications", roles: &[UserRole::Receptionist], redaction: Redaction::Omit },
+    FieldRule { resource: "patients", field: "notes", roles: &[UserRole::Receptionist], redaction: Redaction::Omit },
+    // Visits
+    FieldRule { resource: "visits", field: "clinical_notes", roles: &[UserRole::Nurse, UserRole::Receptionist], redaction: Redaction::Omit },
+    // Diagnoses
+    FieldRule { resource: "diagnoses", field: "clinical_notes", roles: &[UserRole::Nurse, UserRole::Receptionist], redaction: Redaction::Omit },
+];
+
+/// Rules from `policy` that apply to `role` on any of `resources`
+fn rules_for<'a>(
+    policy: &'a [FieldRule],
+    role: &UserRole,
+    resources: &[&str],
+) -> Vec<&'a FieldRule> {
+    policy
+        .iter()
+        .filter(|rule| resources.contains(&rule.resource) && rule.roles.contains(role))
+        .collect()
+}
+
+/// Mask a string, keeping the first three and the last character
+fn mask_string(value: &str) -> String {
+    let chars: Vec<char> = value.chars().collect();
+    if chars.len() < 6 {
+        return "*".repeat(chars.len());
+    }
+    let mut masked: String = chars[..3].iter().collect();
+    masked.push_str(&"*".repeat(chars.len() - 4));
+    masked.push(chars[chars.len() - 1]);
+    masked
+}
+
+/// Apply rules to a JSON value in place (recursively)
+fn redact_value(value: &mut Value, rules: &[&FieldRule]) {
+    match value {
+        Value::Object(map) => {
+            for rule in rules {
+                match rule.redaction {
+                    Redaction::Omit => {
+                        map.remove(rule.field);
+                    }
+                    Redaction::Mask => {
+                        if let Some(Value::String(s)) = map.get_mut(rule.field) {
+                            *s = mask_string(s);
+                        }
+                    }
+                }
+            }
+            for nested in map.values_mut() {
+                redact_value(nested, rules);
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                redact_value(item, rules);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Redact JSON responses according to `FIELD_POLICY`
+///
+/// State: the resources whose rules apply to the route group.
+pub async fn field_redaction_middleware(
+    State(resources): State<&'static [&'static str]>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let rules = match req.extensions().get::<UserRole>() {
+        Some(role) => rules_for(FIELD_POLICY, role, resources),
+        None => Vec::new(),
+    };
+
+    let response = next.run(req).await;
+    // A version conflict carries the current record
+    let has_record =
+        response.status().is_success() || response.status() == StatusCode::CONFLICT;
+    if rules.is_empty() || !has_record {
+        return response;
+    }
+
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|ct| ct.contains("application/json"))
+        .unwrap_or(false);
+    if !is_json {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::error!("Failed to buffer response for field redaction: {}", e);
+            return Response::from_parts(parts, Body::empty());
+        }
+    };
+
+    let mut json: Value = match serde_json::from_slice(&bytes) {
+        Ok(json) => json,
+        Err(_) => return Response::from_parts(parts, Body::from(bytes)),
+    };
+    redact_value(&mut json, &rules);
+
+    let body = serde_json::to_vec(&json).unwrap_or_else(|_| bytes.to_vec());
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_mask_string() {
+        assert_eq!(mask_string("RSSMRA85M01H501U"), "RSS************U");
+        assert_eq!(mask_string("ABC"), "***");
+        assert_eq!(mask_string(""), "");
+    }
+
+    #[test]
+    fn test_rules_for_role_and_resource() {
+        let rules = rules_for(FIELD_POLICY, &UserRole::Receptionist, &["patients"]);
+        assert!(rules.iter().any(|r| r.field == "allergies"));
+        assert!(rules.iter().all(|r| r.resource == "patients"));
+
+        assert!(rules_for(FIELD_POLICY, &UserRole::Doctor, &["patients", "visits", "diagnoses"]).is_empty());
+        assert!(rules_for(FIELD_POLICY, &UserRole::Admin, &["patients", "visits", "diagnoses"]).is_empty());
+    }
+
+    #[test]
+    fn test_redact_nested_values() {
+        let rules = rules_for(FIELD_POLICY, &UserRole::Receptionist, &["patients"]);
+        let mut body = json!({
+            "patients": [
+                { "first_name": "Mario", "allergies": ["Penicillin"], "notes": "x" },
+                { "first_name": "Anna", "blood_type": "A+" }
+            ],
+            "total": 2
+        });
+        redact_value(&mut body, &rules);
+        assert_eq!(
+            body,
+            json!({
+                "patients": [{ "first_name": "Mario" }, { "first_name": "Anna" }],
+                "total": 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_mask_keeps_nulls() {
+        let rules = rules_for(FIELD_POLICY, &UserRole::Nurse, &["patients"]);
+        let mut body = json!([
+            { "fiscal_code": "RSSMRA85M01H501U" },
+            { "fiscal_code": null }
+        ]);
+        redact_value(&mut body, &rules);
+        assert_eq!(
+            body,
+            json!([{ "fiscal_code": "RSS************U" }, { "fiscal_code": null }])
+        );
+    }
+}
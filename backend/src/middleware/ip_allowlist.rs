@@ -0,0 +1,183 @@
+/*!
+ * Admin IP Allowlist Middleware
+ *
+ * Restricts administrative routes (user management, system settings and audit
+ * logs) to configured CIDR ranges. The allowlist is optional:
+ *
+ * - `security.admin_ip_allowlist` (system setting, editable at runtime through
+ *   PUT /api/v1/settings/{key}) takes precedence when it is non-empty
+ * - otherwise `ADMIN_IP_ALLOWLIST` (comma-separated, from `Config`) is used
+ * - when both are empty every client is allowed
+ *
+ * When an allowlist is active, requests without a parseable client IP are
+ * rejected.
+ */
+
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use ipnetwork::IpNetwork;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::handlers::auth::AppState;
+use crate::models::RequestContext;
+use crate::services::SettingsService;
+use crate::utils::AppError;
+
+/// System setting holding the runtime allowlist (JSON array of CIDR strings)
+pub const ADMIN_IP_ALLOWLIST_SETTING: &str = "security.admin_ip_allowlist";
+
+/// Parse CIDR ranges; bare addresses are treated as single-host ranges
+///
+/// # Errors
+///
+/// Returns the first entry that is not a valid address or CIDR range
+pub fn parse_ranges<S: AsRef<str>>(entries: &[S]) -> Result<Vec<IpNetwork>, String> {
+    entries
+        .iter()
+        .map(|entry| entry.as_ref().trim())
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            IpNetwork::from_str(entry).map_err(|_| format!("Invalid IP address or CIDR range: {}", entry))
+        })
+        .collect()
+}
+
+/// Whether `ip` falls within any of the ranges
+pub fn is_allowed(ranges: &[IpNetwork], ip: IpAddr) -> bool {
+    ranges.iter().any(|range| range.contains(ip))
+}
+
+/// Parse a client IP as recorded in the request context
+pub fn parse_client_ip(ip: Option<&str>) -> Option<IpAddr> {
+    ip.and_then(|ip| IpAddr::from_str(ip.trim()).ok())
+}
+
+/// Admin route allowlist: configured ranges plus the runtime setting
+#[derive(Clone, Debug, Default)]
+pub struct AdminIpAllowlist {
+    /// Ranges from `ADMIN_IP_ALLOWLIST`
+    configured: Arc<Vec<IpNetwork>>,
+}
+
+impl AdminIpAllowlist {
+    /// Create an allowlist from configured ranges
+    pub fn new(configured: Vec<IpNetwork>) -> Self {
+        Self {
+            configured: Arc::new(configured),
+        }
+    }
+
+    /// Ranges from configuration
+    pub fn configured(&self) -> &[IpNetwork] {
+        &self.configured
+    }
+
+    /// Ranges currently in effect (empty = allowlist disabled)
+    ///
+    /// The runtime setting wins when it is non-empty. Invalid entries in the
+    /// setting are skipped (updates are validated, so this only happens if
+    /// the database was edited directly).
+    pub async fn effective(&self, settings: &SettingsService) -> Vec<IpNetwork> {
+        let entries: Vec<String> = settings
+            .get_setting_value(ADMIN_IP_ALLOWLIST_SETTING)
+            .await
+            .unwrap_or(None)
+            .unwrap_or_default();
+
+        let ranges: Vec<IpNetwork> = entries
+            .iter()
+            .filter_map(|entry| match IpNetwork::from_str(entry.trim()) {
+                Ok(range) => Some(range),
+                Err(_) => {
+                    tracing::warn!("Ignoring invalid admin IP allowlist entry: {}", entry);
+                    None
+                }
+            })
+            .collect();
+
+        if ranges.is_empty() {
+            self.configured.to_vec()
+        } else {
+            ranges
+        }
+    }
+}
+
+/// Admin IP allowlist middleware
+///
+/// Returns 403 Forbidden when an allowlist is active and the client IP is not
+/// within it.
+pub async fn admin_ip_allowlist_middleware(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let ranges = state
+        .admin_ip_allowlist
+        .effective(&state.settings_service)
+        .await;
+
+    if ranges.is_empty() {
+        return next.run(request).await;
+    }
+
+    let client_ip = request
+        .extensions()
+        .get::<RequestContext>()
+        .and_then(|ctx| parse_client_ip(ctx.ip_address.as_deref()));
+
+    match client_ip {
+        Some(ip) if is_allowed(&ranges, ip) => next.run(request).await,
+        _ => {
+            tracing::warn!(
+                "Admin route {} rejected for client {:?} (not in IP allowlist)",
+                request.uri().path(),
+                client_ip
+            );
+            AppError::Forbidden("Access from this network is not allowed".to_string())
+                .into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ranges_accepts_cidrs_and_addresses() {
+        let ranges = parse_ranges(&["10.0.0.0/8", " 192.168.1.10 ", "", "fd00::/8"]).unwrap();
+        assert_eq!(ranges.len(), 3);
+        assert_eq!(ranges[1].prefix(), 32);
+    }
+
+    #[test]
+    fn test_parse_ranges_rejects_invalid_entries() {
+        let err = parse_ranges(&["10.0.0.0/8", "not-an-ip"]).unwrap_err();
+        assert!(err.contains("not-an-ip"));
+        assert!(parse_ranges(&["10.0.0.0/33"]).is_err());
+    }
+
+    #[test]
+    fn test_is_allowed() {
+        let ranges = parse_ranges(&["10.0.0.0/8", "192.168.1.10"]).unwrap();
+
+        assert!(is_allowed(&ranges, "10.20.30.40".parse().unwrap()));
+        assert!(is_allowed(&ranges, "192.168.1.10".parse().unwrap()));
+        assert!(!is_allowed(&ranges, "192.168.1.11".parse().unwrap()));
+        assert!(!is_allowed(&ranges, "::1".parse().unwrap()));
+        assert!(!is_allowed(&[], "10.0.0.1".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_parse_client_ip() {
+        assert_eq!(parse_client_ip(Some(" 10.0.0.1 ")), Some("10.0.0.1".parse().unwrap()));
+        assert_eq!(parse_client_ip(Some("unknown")), None);
+        assert_eq!(parse_client_ip(None), None);
+    }
+}
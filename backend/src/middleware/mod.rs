@@ -8,26 +8,48 @@
 // JWT Authentication middleware
 pub mod auth;
 
+// Patient portal authentication (portal tokens only)
+pub mod portal_auth;
+
 // Session timeout tracking
 pub mod session_timeout;
 
+// Revoked access/refresh token tracking
+pub mod token_denylist;
+
 // Authorization middleware with Casbin RBAC
 #[cfg(feature = "rbac")]
 pub mod authorization;
 
-// Rate limiting middleware (handled by Nginx in production)
-#[allow(dead_code)]
+// Casbin policy persistence in PostgreSQL (runtime policy management)
+#[cfg(feature = "rbac")]
+pub mod casbin_adapter;
+
+// Rate limiting middleware (per-user/per-IP quotas on API routes;
+// failed login throttling is applied to the login route)
 pub mod rate_limit;
 
+// Request counter stores of the rate limiter (memory, Postgres or Redis)
+pub mod rate_limit_store;
+
+// Admin route IP allowlist (user management, settings, audit logs)
+pub mod ip_allowlist;
+
 // Audit logging middleware
 pub mod audit;
 
+// Client IP resolution through trusted reverse proxies
+pub mod client_ip;
+
 // Request context extraction middleware
 pub mod request_context;
 
 // CORS configuration
 pub mod cors;
 
+// Response compression (brotli/gzip)
+pub mod compression;
+
 // Security headers middleware (handled by Nginx in production)
 #[allow(dead_code)]
 pub mod security_headers;
@@ -42,3 +64,9 @@ pub mod request_validation;
 
 // Error redaction middleware (prevents leaking implementation details)
 pub mod error_redaction;
+
+// Role-based field redaction for JSON responses
+pub mod field_redaction;
+
+// API v2 response envelope
+pub mod api_envelope;
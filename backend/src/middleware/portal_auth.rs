@@ -0,0 +1,94 @@
+/*!
+ * Patient Portal Authentication Middleware
+ *
+ * Validates patient portal tokens and adds the signed-in patient to request
+ * extensions. Staff access tokens are rejected here, and portal tokens are
+ * rejected by `jwt_auth_middleware`, so the two route groups never share a
+ * caller. The portal session is checked on every request, so signing out, an
+ * expired session, a deactivated, merged or erased record and turning the
+ * portal off all take effect immediately.
+ */
+
+use axum::{
+    body::Body,
+    extract::State,
+    http::{Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use uuid::Uuid;
+
+use crate::{
+    handlers::{auth::AppState, patient_portal::portal_service},
+    models::PortalPatient,
+    services::PatientPortalService,
+};
+
+/// Patient Portal Authentication Middleware
+///
+/// Extracts and validates the portal token from the Authorization header,
+/// then adds `PortalPatient` as a request extension.
+pub async fn portal_auth_middleware(
+    State(state): State<AppState>,
+    mut req: Request<Body>,
+    next: Next,
+) -> Result<Response, impl IntoResponse> {
+    if !PatientPortalService::is_enabled(&state.settings_service).await {
+        return Err((StatusCode::NOT_FOUND, "Patient portal is not enabled"));
+    }
+
+    let token = match req
+        .headers()
+        .get("authorization")
+        .and_then(|h| h.to_str().ok())
+    {
+        Some(header) if header.starts_with("Bearer ") => &header[7..],
+        _ => {
+            return Err((
+                StatusCode::UNAUTHORIZED,
+                "Missing or invalid authorization header",
+            ));
+        }
+    };
+
+    let claims = match state.auth_service.validate_portal_token(token) {
+        Ok(claims) => claims,
+        Err(_) => {
+            return Err((StatusCode::UNAUTHORIZED, "Invalid or expired token"));
+        }
+    };
+
+    let (Ok(patient_id), Ok(session_id)) =
+        (Uuid::parse_str(&claims.sub), Uuid::parse_str(&claims.sid))
+    else {
+        return Err((StatusCode::UNAUTHORIZED, "Invalid or expired token"));
+    };
+
+    let Ok(service) = portal_service(&state) else {
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Encryption key not configured",
+        ));
+    };
+
+    match service.authenticate(patient_id, session_id).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return Err((StatusCode::UNAUTHORIZED, "Session expired or invalidated"));
+        }
+        Err(e) => {
+            tracing::error!("Failed to check portal session {}: {}", session_id, e);
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to check session",
+            ));
+        }
+    }
+
+    req.extensions_mut().insert(PortalPatient {
+        patient_id,
+        session_id,
+    });
+
+    Ok(next.run(req).await)
+}
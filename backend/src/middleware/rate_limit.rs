@@ -1,38 +1,61 @@
 /*!
  * Rate Limiting Middleware
  *
- * Implements per-IP and per-user rate limiting to prevent abuse and DoS attacks.
- * Uses the tower-governor crate with in-memory state.
+ * Limits requests per user (authenticated) or per client IP
+ * (unauthenticated) in fixed one-minute windows. Counters are kept in the
+ * store selected with RATE_LIMIT_BACKEND (in memory by default, or in
+ * Postgres or Redis to share them between replicas; see `rate_limit_store`).
  *
- * Rate Limits (per minute):
+ * Rate Limits (per minute, see `RateLimitConfig::from_env`):
  * - Unauthenticated (by IP): 100 requests/minute
  * - Authenticated (by user ID): 300 requests/minute
- * - Bulk operations: 10 requests/minute
+ * - Bulk operations: 10 requests/minute (also patient portal sign-in, online
+ *   booking requests, appointment reminder links and visit surveys, by IP)
+ *
+ * Administrators can give a user other limits at runtime (`rate_limit_quotas`
+ * table, `/api/v1/rate-limits`); the quotas are cached here and reloaded
+ * every minute.
  *
  * Headers returned:
  * - X-RateLimit-Limit: Maximum requests per window
  * - X-RateLimit-Remaining: Requests remaining in current window
  * - X-RateLimit-Reset: Unix timestamp when the window resets
  * - Retry-After: Seconds to wait before retrying (on 429 response)
+ *
+ * Login brute-force protection (`LoginThrottle`):
+ * Account lockout (`max_failed_login_attempts`) does not stop credential
+ * stuffing that rotates usernames, so failed logins are also counted in
+ * sliding windows per client IP and per IP+username, in the same store as the
+ * request counters. Exceeding either limit bans that key for a while and
+ * records an audit entry. Failures per username (from any IP) are counted
+ * too, only to require a CAPTCHA.
+ * - Per IP: 20 failures / 10 minutes
+ * - Per IP+username: 5 failures / 10 minutes
+ * - Ban duration: 15 to 30 minutes
  */
 
 use axum::{
-    extract::Request,
-    http::{HeaderMap, StatusCode},
+    body::{to_bytes, Body},
+    extract::{OriginalUri, Request, State},
+    http::{header::RETRY_AFTER, HeaderMap, StatusCode},
     middleware::Next,
     response::{IntoResponse, Response},
-    Extension,
 };
-use governor::{
-    clock::{Clock, DefaultClock},
-    state::{InMemoryState, direct::NotKeyed},
-    Quota, RateLimiter as GovernorRateLimiter,
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
-use std::{num::NonZeroU32, sync::Arc};
 use uuid::Uuid;
 
-/// Rate limiter type for simple (non-keyed) rate limiting
-type SimpleRateLimiter = Arc<GovernorRateLimiter<NotKeyed, InMemoryState, DefaultClock>>;
+use crate::handlers::auth::AppState;
+use crate::middleware::audit::extract_user_from_auth_header;
+use crate::middleware::rate_limit_store::{MemoryRateLimitStore, RateLimitStore, WindowHit};
+use crate::models::{AuditAction, AuditLog, CreateAuditLog, EntityType, RequestContext};
+use crate::utils::AppError;
+
+/// Length of a rate limit window
+pub const RATE_LIMIT_WINDOW_SECS: u64 = 60;
 
 /// Configuration for different rate limit tiers
 #[derive(Clone, Debug)]
@@ -55,17 +78,112 @@ impl Default for RateLimitConfig {
     }
 }
 
-/// Rate limiting layer that can be added to Axum router
+impl RateLimitConfig {
+    /// Read the limits from `RATE_LIMIT_UNAUTHENTICATED`,
+    /// `RATE_LIMIT_AUTHENTICATED` and `RATE_LIMIT_BULK` (defaults if unset)
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let limit = |name: &str, default: u32| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse::<u32>().ok())
+                .filter(|v| *v > 0)
+                .unwrap_or(default)
+        };
+
+        Self {
+            unauthenticated_limit: limit("RATE_LIMIT_UNAUTHENTICATED", defaults.unauthenticated_limit),
+            authenticated_limit: limit("RATE_LIMIT_AUTHENTICATED", defaults.authenticated_limit),
+            bulk_limit: limit("RATE_LIMIT_BULK", defaults.bulk_limit),
+        }
+    }
+}
+
+/// Rate limit tier of a request
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RateLimitTier {
+    Unauthenticated,
+    Authenticated,
+    Bulk,
+}
+
+impl RateLimitTier {
+    /// Tier of a request to `path`
+    pub fn for_request(path: &str, authenticated: bool) -> Self {
+        let is_bulk = path.contains("/bulk")
+            || path.contains("/batch")
+            || path.contains("/export")
+            || path.contains("/import")
+            || (path.contains("/cohorts/")
+                && (path.ends_with("/documents") || path.ends_with("/notifications")))
+            || (path.contains("/recalls/rules/") && path.ends_with("/run"))
+            // Patient portal sign-in is public and emails or issues tokens
+            || path.starts_with("/api/portal/v1/auth/")
+            // So are online booking requests, appointment reminder links,
+            // visit survey answers, televisit links and waitlist offer
+            // links, which write without a sign-in
+            || path.starts_with("/api/booking/v1/requests")
+            || path.contains("/appointment-links/")
+            || path.ends_with("/surveys/respond")
+            || path.starts_with("/api/v1/televisit/")
+            || path.contains("/waitlist-offers/");
+
+        if is_bulk {
+            RateLimitTier::Bulk
+        } else if authenticated {
+            RateLimitTier::Authenticated
+        } else {
+            RateLimitTier::Unauthenticated
+        }
+    }
+
+    /// Counter key of a client in this tier (bulk requests are counted apart)
+    pub fn key(&self, client: &str) -> String {
+        match self {
+            RateLimitTier::Bulk => format!("bulk:{}", client),
+            _ => client.to_string(),
+        }
+    }
+}
+
+/// Counter key of a user
+pub fn user_client_key(user_id: Uuid) -> String {
+    format!("user:{}", user_id)
+}
+
+/// Limits of a user set by an administrator
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UserQuota {
+    pub requests_per_minute: u32,
+    pub bulk_requests_per_minute: u32,
+}
+
+/// Outcome of a rate limit check
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RateLimitStatus {
+    pub limit: u32,
+    pub remaining: u32,
+    /// Unix timestamp when the window resets
+    pub reset_at: u64,
+    pub allowed: bool,
+}
+
+impl RateLimitStatus {
+    /// Seconds until the window resets (at least 1)
+    pub fn retry_after(&self, now: u64) -> u64 {
+        self.reset_at.saturating_sub(now).max(1)
+    }
+}
+
+/// Rate limiter shared by all requests of this instance
 ///
-/// This layer maintains separate rate limiters for different tiers of usage.
-/// The middleware function determines which limiter to apply based on authentication
-/// status and request path.
+/// Keeps the user quotas and counts requests per client and tier in its
+/// store, which may be shared with other instances (see `rate_limit_store`).
 #[derive(Clone)]
 pub struct RateLimitLayer {
     config: RateLimitConfig,
-    unauthenticated_limiter: SimpleRateLimiter,
-    authenticated_limiter: SimpleRateLimiter,
-    bulk_limiter: SimpleRateLimiter,
+    store: Arc<dyn RateLimitStore>,
+    quotas: Arc<RwLock<HashMap<Uuid, UserQuota>>>,
 }
 
 impl RateLimitLayer {
@@ -75,25 +193,18 @@ impl RateLimitLayer {
         Self::with_config(config)
     }
 
-    /// Create a new rate limit layer with custom configuration
+    /// Create a new rate limit layer with custom configuration and
+    /// in-memory counters
     pub fn with_config(config: RateLimitConfig) -> Self {
-        let unauthenticated_limiter = Arc::new(GovernorRateLimiter::direct(
-            Quota::per_minute(NonZeroU32::new(config.unauthenticated_limit).unwrap())
-        ));
-
-        let authenticated_limiter = Arc::new(GovernorRateLimiter::direct(
-            Quota::per_minute(NonZeroU32::new(config.authenticated_limit).unwrap())
-        ));
-
-        let bulk_limiter = Arc::new(GovernorRateLimiter::direct(
-            Quota::per_minute(NonZeroU32::new(config.bulk_limit).unwrap())
-        ));
+        Self::with_store(config, Arc::new(MemoryRateLimitStore::new()))
+    }
 
+    /// Create a new rate limit layer counting requests in the given store
+    pub fn with_store(config: RateLimitConfig, store: Arc<dyn RateLimitStore>) -> Self {
         Self {
             config,
-            unauthenticated_limiter,
-            authenticated_limiter,
-            bulk_limiter,
+            store,
+            quotas: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 
@@ -102,19 +213,87 @@ impl RateLimitLayer {
         &self.config
     }
 
-    /// Get the unauthenticated rate limiter
-    pub fn unauthenticated(&self) -> SimpleRateLimiter {
-        Arc::clone(&self.unauthenticated_limiter)
+    /// Name of the counter store
+    pub fn store_name(&self) -> &'static str {
+        self.store.name()
+    }
+
+    /// Quota of a user, if an administrator set one
+    pub fn quota(&self, user_id: Uuid) -> Option<UserQuota> {
+        self.quotas.read().unwrap().get(&user_id).copied()
+    }
+
+    /// Set or clear the quota of a user
+    pub fn set_quota(&self, user_id: Uuid, quota: Option<UserQuota>) {
+        let mut quotas = self.quotas.write().unwrap();
+        match quota {
+            Some(quota) => quotas.insert(user_id, quota),
+            None => quotas.remove(&user_id),
+        };
+    }
+
+    /// Replace all user quotas (after loading them from the database)
+    pub fn replace_quotas(&self, quotas: HashMap<Uuid, UserQuota>) {
+        *self.quotas.write().unwrap() = quotas;
+    }
+
+    /// Limit per window of a tier, for a user if authenticated
+    pub fn limit_for(&self, tier: RateLimitTier, user_id: Option<Uuid>) -> u32 {
+        let quota = user_id.and_then(|id| self.quota(id));
+        match (tier, quota) {
+            (RateLimitTier::Bulk, Some(quota)) => quota.bulk_requests_per_minute,
+            (RateLimitTier::Bulk, None) => self.config.bulk_limit,
+            (RateLimitTier::Authenticated, Some(quota)) => quota.requests_per_minute,
+            (RateLimitTier::Authenticated, None) => self.config.authenticated_limit,
+            (RateLimitTier::Unauthenticated, _) => self.config.unauthenticated_limit,
+        }
+    }
+
+    /// Count a request against a key, unless its limit is reached
+    ///
+    /// If the store is unreachable the request is allowed: an outage of a
+    /// shared store must not take the API down with it.
+    pub async fn check(&self, key: &str, limit: u32) -> RateLimitStatus {
+        self.check_at(key, limit, unix_now()).await
+    }
+
+    async fn check_at(&self, key: &str, limit: u32, now: u64) -> RateLimitStatus {
+        let index = now / RATE_LIMIT_WINDOW_SECS;
+        let hit = match self.store.hit(key, index, limit).await {
+            Ok(hit) => hit,
+            Err(e) => {
+                tracing::warn!("Rate limit store ({}) failed: {:#}", self.store.name(), e);
+                WindowHit {
+                    count: 0,
+                    allowed: true,
+                }
+            }
+        };
+
+        RateLimitStatus {
+            limit,
+            remaining: limit.saturating_sub(hit.count),
+            reset_at: (index + 1) * RATE_LIMIT_WINDOW_SECS,
+            allowed: hit.allowed,
+        }
+    }
+
+    /// Requests counted against a key in the current window
+    pub async fn usage(&self, key: &str) -> u32 {
+        self.usage_at(key, unix_now()).await
     }
 
-    /// Get the authenticated rate limiter
-    pub fn authenticated(&self) -> SimpleRateLimiter {
-        Arc::clone(&self.authenticated_limiter)
+    async fn usage_at(&self, key: &str, now: u64) -> u32 {
+        let index = now / RATE_LIMIT_WINDOW_SECS;
+        self.store.usage(key, index).await.unwrap_or_else(|e| {
+            tracing::warn!("Rate limit store ({}) failed: {:#}", self.store.name(), e);
+            0
+        })
     }
 
-    /// Get the bulk operations rate limiter
-    pub fn bulk(&self) -> SimpleRateLimiter {
-        Arc::clone(&self.bulk_limiter)
+    /// Drop the counters of past windows
+    pub async fn prune(&self) -> anyhow::Result<()> {
+        self.store.prune(unix_now() / RATE_LIMIT_WINDOW_SECS).await
     }
 }
 
@@ -124,115 +303,513 @@ impl Default for RateLimitLayer {
     }
 }
 
+/// Current Unix timestamp in seconds
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
 /// Rate limiting middleware function
 ///
-/// This middleware checks rate limits based on authentication status:
-/// - Unauthenticated requests: Limited to 100/min (shared per instance)
-/// - Authenticated requests: Limited to 300/min (per user, but shared in this implementation)
-/// - Bulk operations: Special endpoints limited to 10/min
-///
-/// Returns 429 Too Many Requests if rate limit is exceeded.
+/// Counts the request against the user (valid bearer token) or the client
+/// IP, with the limit of its tier:
+/// - Unauthenticated requests: 100/min per IP
+/// - Authenticated requests: 300/min per user, or the user's quota
+/// - Bulk operations (bulk, batch, export and import endpoints, patient
+///   portal sign-in, online booking requests, appointment reminder links,
+///   visit surveys): 10/min, or the user's bulk quota
 ///
-/// Note: This implementation uses a simple per-instance rate limiter rather than
-/// per-IP or per-user tracking. For production use with multiple instances or
-/// stricter per-user limits, consider using Redis-backed rate limiting.
+/// Returns 429 Too Many Requests with `Retry-After` once the limit is
+/// reached. Requests without a known client IP or user are not limited.
 pub async fn rate_limit_middleware(
-    Extension(rate_limiter): Extension<RateLimitLayer>,
-    user_id: Option<Extension<Uuid>>,
+    State(state): State<AppState>,
     request: Request,
     next: Next,
-) -> Result<Response, impl IntoResponse> {
-    // Determine if this is a bulk operation endpoint
-    let is_bulk = request.uri().path().contains("/bulk")
-        || request.uri().path().contains("/export")
-        || request.uri().path().contains("/import");
-
-    // Determine the appropriate rate limiter and limit value
-    let (limiter, limit) = if is_bulk {
-        // Bulk operations: always use strictest limit
-        (rate_limiter.bulk(), rate_limiter.config().bulk_limit)
-    } else if user_id.is_some() {
-        // Authenticated: use higher limit
-        (rate_limiter.authenticated(), rate_limiter.config().authenticated_limit)
-    } else {
-        // Unauthenticated: use default limit
-        (rate_limiter.unauthenticated(), rate_limiter.config().unauthenticated_limit)
+) -> Response {
+    let user_id = extract_user_from_auth_header(&request, &state).map(|(user_id, _)| user_id);
+    let client = match user_id {
+        Some(user_id) => user_client_key(user_id),
+        None => match request
+            .extensions()
+            .get::<RequestContext>()
+            .and_then(|ctx| ctx.ip_address.clone())
+        {
+            Some(ip) => format!("ip:{}", ip),
+            None => return next.run(request).await,
+        },
     };
 
-    // Check the rate limit
-    match limiter.check() {
-        Ok(_) => {
-            // Rate limit check passed, proceed with request
-            let response = next.run(request).await;
-
-            // Add rate limit headers to response
-            let mut response = response.into_response();
-            add_rate_limit_headers(
-                response.headers_mut(),
-                limit,
-                true,
-            );
+    // Full path, also when mounted under a nested router
+    let path = request
+        .extensions()
+        .get::<OriginalUri>()
+        .map_or_else(|| request.uri().path().to_string(), |uri| uri.path().to_string());
 
-            Ok(response)
-        }
-        Err(not_until) => {
-            // Rate limit exceeded
-            let wait_time = not_until.wait_time_from(DefaultClock::default().now());
-            let retry_after_secs = wait_time.as_secs();
+    let limiter = &state.rate_limiter;
+    let tier = RateLimitTier::for_request(&path, user_id.is_some());
+    let status = limiter
+        .check(&tier.key(&client), limiter.limit_for(tier, user_id))
+        .await;
 
-            // Create headers for 429 response
-            let mut headers = HeaderMap::new();
-            add_rate_limit_headers(&mut headers, limit, false);
-            headers.insert(
-                "Retry-After",
-                retry_after_secs.to_string().parse().unwrap(),
-            );
-
-            Err((StatusCode::TOO_MANY_REQUESTS, headers))
-        }
+    if !status.allowed {
+        tracing::warn!("Rate limit exceeded for {} ({:?})", client, tier);
+        let mut response = AppError::RateLimitExceeded.into_response();
+        add_rate_limit_headers(response.headers_mut(), &status);
+        response.headers_mut().insert(
+            RETRY_AFTER,
+            status.retry_after(unix_now()).to_string().parse().unwrap(),
+        );
+        return response;
     }
+
+    let mut response = next.run(request).await;
+    add_rate_limit_headers(response.headers_mut(), &status);
+    response
 }
 
 /// Add rate limit headers to the response
 ///
 /// Adds the following headers:
 /// - X-RateLimit-Limit: Maximum requests allowed in the time window
-/// - X-RateLimit-Remaining: Number of requests remaining (approximate)
+/// - X-RateLimit-Remaining: Number of requests remaining in the window
 /// - X-RateLimit-Reset: Unix timestamp when the rate limit window resets
-fn add_rate_limit_headers(
-    headers: &mut HeaderMap,
-    limit: u32,
-    has_quota: bool,
-) {
-    // Add headers
+fn add_rate_limit_headers(headers: &mut HeaderMap, status: &RateLimitStatus) {
     headers.insert(
         "X-RateLimit-Limit",
-        limit.to_string().parse().unwrap(),
+        status.limit.to_string().parse().unwrap(),
     );
-
-    // Remaining is approximate - we know if we have quota or not
-    let remaining = if has_quota {
-        limit.saturating_sub(1) // We just consumed one request
-    } else {
-        0
-    };
-
     headers.insert(
         "X-RateLimit-Remaining",
-        remaining.to_string().parse().unwrap(),
+        status.remaining.to_string().parse().unwrap(),
     );
-
-    // Calculate reset time (60 seconds from now for per-minute quota)
-    // Use system time for the reset timestamp since QuantaInstant is not a Unix timestamp
-    use std::time::{SystemTime, UNIX_EPOCH};
-    let reset_time = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs() + 60;
     headers.insert(
         "X-RateLimit-Reset",
-        reset_time.to_string().parse().unwrap(),
+        status.reset_at.to_string().parse().unwrap(),
+    );
+}
+
+// ========== LOGIN BRUTE-FORCE PROTECTION ==========
+
+/// Maximum login request body read to extract the username
+const MAX_LOGIN_BODY_BYTES: usize = 16 * 1024;
+
+/// Client key of login requests without a known client IP
+pub const UNKNOWN_CLIENT: &str = "unknown";
+
+/// Limit of failure counters, which are never capped (the Postgres store
+/// keeps counts as INTEGER)
+const UNLIMITED: u32 = i32::MAX as u32;
+
+/// Configuration for failed login throttling
+#[derive(Clone, Debug)]
+pub struct LoginThrottleConfig {
+    /// Sliding window in which failures are counted
+    pub window: Duration,
+    /// Failed logins allowed per client IP within the window
+    pub max_failures_per_ip: usize,
+    /// Failed logins allowed per client IP and username within the window
+    pub max_failures_per_ip_username: usize,
+    /// How long a key is banned once a limit is exceeded (at least)
+    pub ban_duration: Duration,
+}
+
+impl Default for LoginThrottleConfig {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_secs(600),
+            max_failures_per_ip: 20,
+            max_failures_per_ip_username: 5,
+            ban_duration: Duration::from_secs(900),
+        }
+    }
+}
+
+/// Scope of a login ban
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BanScope {
+    Ip,
+    IpUsername,
+}
+
+impl BanScope {
+    fn as_str(&self) -> &'static str {
+        match self {
+            BanScope::Ip => "ip",
+            BanScope::IpUsername => "ip_username",
+        }
+    }
+}
+
+/// Failed login tracker keyed by client IP and IP+username
+///
+/// Failures and bans are kept in the rate limit store, so replicas sharing a
+/// Postgres or Redis store enforce the limits together. Failures are counted
+/// per window-length period; the sliding window adds the count of the
+/// previous period weighted by the share of the window still covering it.
+/// A ban marker covers the ban period it was set in and the next one, so a
+/// ban lasts between one and two `ban_duration`s. If the store is unreachable
+/// logins are let through.
+#[derive(Clone)]
+pub struct LoginThrottle {
+    config: LoginThrottleConfig,
+    store: Arc<dyn RateLimitStore>,
+}
+
+impl LoginThrottle {
+    /// Create a login throttle with default configuration
+    pub fn new() -> Self {
+        Self::with_config(LoginThrottleConfig::default())
+    }
+
+    /// Create a login throttle with custom configuration and in-memory
+    /// counters
+    pub fn with_config(config: LoginThrottleConfig) -> Self {
+        Self::with_store(config, Arc::new(MemoryRateLimitStore::new()))
+    }
+
+    /// Create a login throttle keeping its counters in the given store
+    pub fn with_store(config: LoginThrottleConfig, store: Arc<dyn RateLimitStore>) -> Self {
+        Self { config, store }
+    }
+
+    /// Get the configuration
+    pub fn config(&self) -> &LoginThrottleConfig {
+        &self.config
+    }
+
+    fn ip_key(ip: &str) -> String {
+        format!("login:ip:{}", ip)
+    }
+
+    fn ip_username_key(ip: &str, username: &str) -> String {
+        format!("login:ipu:{}:{}", ip, username.trim().to_lowercase())
+    }
+
+    fn username_key(username: &str) -> String {
+        format!("login:user:{}", username.trim().to_lowercase())
+    }
+
+    /// Store window of a counter living until the end of `period` (of
+    /// `length` seconds): its last minute, so pruning keeps it until then
+    fn period_window(period: u64, length: u64) -> u64 {
+        ((period + 1) * length - 1) / RATE_LIMIT_WINDOW_SECS
+    }
+
+    fn window_secs(&self) -> u64 {
+        self.config.window.as_secs().max(1)
+    }
+
+    /// Store window of the failure counter of `period`, kept through the next
+    /// period where it is the previous one of the sliding window
+    fn counter_window(&self, period: u64) -> u64 {
+        Self::period_window(period + 1, self.window_secs())
+    }
+
+    fn ban_secs(&self) -> u64 {
+        self.config.ban_duration.as_secs().max(1)
+    }
+
+    fn store_failed(&self, e: anyhow::Error) {
+        tracing::warn!(
+            "Login throttle store ({}) failed: {:#}",
+            self.store.name(),
+            e
+        );
+    }
+
+    /// Remaining ban time for this IP (and username, if known)
+    pub async fn banned_for(&self, ip: &str, username: Option<&str>) -> Option<Duration> {
+        self.banned_for_at(ip, username, unix_now()).await
+    }
+
+    async fn banned_for_at(&self, ip: &str, username: Option<&str>, now: u64) -> Option<Duration> {
+        let mut keys = vec![Self::ip_key(ip)];
+        if let Some(username) = username {
+            keys.push(Self::ip_username_key(ip, username));
+        }
+
+        let ban = self.ban_secs();
+        let current = now / ban;
+        let mut remaining = None;
+        for key in keys {
+            // A ban set in the current period outlasts one from the previous
+            for period in [current, current.saturating_sub(1)] {
+                let marker = format!("{}:ban:{}", key, period);
+                match self
+                    .store
+                    .usage(&marker, Self::period_window(period + 1, ban))
+                    .await
+                {
+                    Ok(0) => continue,
+                    Ok(_) => {
+                        let until = (period + 2) * ban;
+                        remaining = remaining.max(Some(Duration::from_secs(until - now)));
+                        break;
+                    }
+                    Err(e) => self.store_failed(e),
+                }
+            }
+        }
+
+        remaining
+    }
+
+    /// Failed logins within the sliding window from this IP or against this
+    /// username (from any IP), whichever is higher
+    pub async fn recent_failures(&self, ip: &str, username: &str) -> usize {
+        self.recent_failures_at(ip, username, unix_now()).await
+    }
+
+    async fn recent_failures_at(&self, ip: &str, username: &str, now: u64) -> usize {
+        let ip_failures = self.failures_at(&Self::ip_key(ip), now).await;
+        let username_failures = self.failures_at(&Self::username_key(username), now).await;
+        ip_failures.max(username_failures)
+    }
+
+    async fn failures_at(&self, key: &str, now: u64) -> usize {
+        let period = now / self.window_secs();
+        let counter = format!("{}:{}", key, period);
+        match self
+            .store
+            .usage(&counter, self.counter_window(period))
+            .await
+        {
+            Ok(count) => count as usize + self.previous_failures(key, now).await,
+            Err(e) => {
+                self.store_failed(e);
+                0
+            }
+        }
+    }
+
+    /// Failures of the previous period still inside the sliding window
+    ///
+    /// The previous count is weighted by the share of the window that still
+    /// covers that period, rounded up.
+    async fn previous_failures(&self, key: &str, now: u64) -> usize {
+        let length = self.window_secs();
+        let Some(previous) = (now / length).checked_sub(1) else {
+            return 0;
+        };
+        let counter = format!("{}:{}", key, previous);
+        match self
+            .store
+            .usage(&counter, self.counter_window(previous))
+            .await
+        {
+            Ok(count) => (u64::from(count) * (length - now % length)).div_ceil(length) as usize,
+            Err(e) => {
+                self.store_failed(e);
+                0
+            }
+        }
+    }
+
+    /// Record a failed login
+    ///
+    /// Returns the scopes newly banned by this failure.
+    pub async fn record_failure(&self, ip: &str, username: Option<&str>) -> Vec<BanScope> {
+        self.record_failure_at(ip, username, unix_now()).await
+    }
+
+    async fn record_failure_at(&self, ip: &str, username: Option<&str>, now: u64) -> Vec<BanScope> {
+        let mut tracked = vec![(
+            Self::ip_key(ip),
+            self.config.max_failures_per_ip,
+            BanScope::Ip,
+        )];
+        if let Some(username) = username {
+            tracked.push((
+                Self::ip_username_key(ip, username),
+                self.config.max_failures_per_ip_username,
+                BanScope::IpUsername,
+            ));
+        }
+
+        let period = now / self.window_secs();
+        let window = self.counter_window(period);
+        // The username alone is counted for the CAPTCHA but never banned, so
+        // failures from other clients cannot lock the account owner out
+        if let Some(username) = username {
+            let counter = format!("{}:{}", Self::username_key(username), period);
+            if let Err(e) = self.store.hit(&counter, window, UNLIMITED).await {
+                self.store_failed(e);
+            }
+        }
+
+        let mut banned = Vec::new();
+        for (key, max_failures, scope) in tracked {
+            let counter = format!("{}:{}", key, period);
+            let failures = match self.store.hit(&counter, window, UNLIMITED).await {
+                Ok(hit) => hit.count as usize + self.previous_failures(&key, now).await,
+                Err(e) => {
+                    self.store_failed(e);
+                    continue;
+                }
+            };
+
+            if failures > max_failures {
+                self.reset_failures(&key, period).await;
+                if self.ban(&key, now).await {
+                    banned.push(scope);
+                }
+            }
+        }
+
+        banned
+    }
+
+    /// Set the ban marker of a key; false if another request already set it
+    async fn ban(&self, key: &str, now: u64) -> bool {
+        let ban = self.ban_secs();
+        let period = now / ban;
+        let marker = format!("{}:ban:{}", key, period);
+        match self
+            .store
+            .hit(&marker, Self::period_window(period + 1, ban), 1)
+            .await
+        {
+            Ok(hit) => hit.allowed,
+            Err(e) => {
+                self.store_failed(e);
+                false
+            }
+        }
+    }
+
+    /// Clear the IP+username and username failure counts after a successful
+    /// login
+    ///
+    /// The per-IP count is kept so a valid account cannot be used to reset
+    /// the window while guessing others.
+    pub async fn record_success(&self, ip: &str, username: &str) {
+        self.record_success_at(ip, username, unix_now()).await
+    }
+
+    async fn record_success_at(&self, ip: &str, username: &str, now: u64) {
+        let period = now / self.window_secs();
+        for key in [Self::ip_username_key(ip, username), Self::username_key(username)] {
+            self.reset_failures(&key, period).await;
+        }
+    }
+
+    /// Drop the failure counters of a key in the sliding window ending in
+    /// `period`
+    async fn reset_failures(&self, key: &str, period: u64) {
+        for period in [period, period.saturating_sub(1)] {
+            let counter = format!("{}:{}", key, period);
+            let window = self.counter_window(period);
+            if let Err(e) = self.store.reset(&counter, window).await {
+                self.store_failed(e);
+            }
+        }
+    }
+}
+
+impl Default for LoginThrottle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Extract the username from a login request body
+fn login_username(body: &[u8]) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_slice(body).ok()?;
+    value
+        .get("username")?
+        .as_str()
+        .map(|u| u.trim().to_lowercase())
+        .filter(|u| !u.is_empty())
+}
+
+/// Build a 429 response for a banned client
+fn too_many_login_attempts(retry_after: Duration) -> Response {
+    let mut response = AppError::RateLimitExceeded.into_response();
+    response.headers_mut().insert(
+        "Retry-After",
+        retry_after.as_secs().max(1).to_string().parse().unwrap(),
     );
+    response
+}
+
+/// Failed login throttling middleware for the login route
+///
+/// Rejects banned IPs (and IP+username pairs) with 429 before credentials are
+/// checked, counts 401 responses as failures and writes an audit entry when
+/// a new ban is applied. Requests without a known client IP share one key.
+pub async fn login_throttle_middleware(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let request_ctx = request
+        .extensions()
+        .get::<RequestContext>()
+        .cloned()
+        .unwrap_or_else(RequestContext::empty);
+
+    let ip = request_ctx
+        .ip_address
+        .clone()
+        .unwrap_or_else(|| UNKNOWN_CLIENT.to_string());
+
+    // Buffer the body to read the username, then hand it on unchanged
+    let (parts, body) = request.into_parts();
+    let bytes = match to_bytes(body, MAX_LOGIN_BODY_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(_) => return StatusCode::PAYLOAD_TOO_LARGE.into_response(),
+    };
+    let username = login_username(&bytes);
+    let request = Request::from_parts(parts, Body::from(bytes));
+
+    let throttle = &state.login_throttle;
+    if let Some(retry_after) = throttle.banned_for(&ip, username.as_deref()).await {
+        tracing::warn!("Login rejected for banned client {}", ip);
+        return too_many_login_attempts(retry_after);
+    }
+
+    let response = next.run(request).await;
+
+    if response.status() == StatusCode::UNAUTHORIZED {
+        for scope in throttle.record_failure(&ip, username.as_deref()).await {
+            tracing::warn!(
+                "Login ban applied to {} (scope: {}) after repeated failures",
+                ip,
+                scope.as_str()
+            );
+            let banned_username = match scope {
+                BanScope::IpUsername => username.clone(),
+                BanScope::Ip => None,
+            };
+            let _ = AuditLog::create(
+                &state.pool,
+                CreateAuditLog {
+                    user_id: None,
+                    action: AuditAction::Login,
+                    entity_type: EntityType::User,
+                    entity_id: None,
+                    changes: Some(serde_json::json!({
+                        "event": "login_ban",
+                        "scope": scope.as_str(),
+                        "username": banned_username,
+                        "ban_seconds": throttle.config().ban_duration.as_secs(),
+                    })),
+                    ip_address: request_ctx.ip_address.clone(),
+                    user_agent: request_ctx.user_agent.clone(),
+                    request_id: Some(request_ctx.request_id),
+                },
+            )
+            .await;
+        }
+    } else if response.status().is_success() {
+        if let Some(username) = username.as_deref() {
+            throttle.record_success(&ip, username).await;
+        }
+    }
+
+    response
 }
 
 #[cfg(test)]
@@ -249,16 +826,6 @@ mod tests {
         assert_eq!(config.bulk_limit, 10);
     }
 
-    #[test]
-    fn test_rate_limit_layer_creation() {
-        let layer = RateLimitLayer::new();
-
-        // Verify limiters are created
-        assert!(Arc::strong_count(&layer.unauthenticated_limiter) >= 1);
-        assert!(Arc::strong_count(&layer.authenticated_limiter) >= 1);
-        assert!(Arc::strong_count(&layer.bulk_limiter) >= 1);
-    }
-
     #[test]
     fn test_rate_limit_layer_with_custom_config() {
         let config = RateLimitConfig {
@@ -275,84 +842,217 @@ mod tests {
         assert_eq!(layer.config().bulk_limit, 5);
     }
 
-    #[test]
-    fn test_rate_limiter_enforcement_unauthenticated() {
+    #[tokio::test]
+    async fn test_rate_limiter_enforcement_unauthenticated() {
         let layer = RateLimitLayer::new();
-        let limiter = layer.unauthenticated();
+        let limit = layer.limit_for(RateLimitTier::Unauthenticated, None);
+        let now = 1_800_000_000;
 
         // Should allow up to 100 requests
         for i in 1..=100 {
-            assert!(
-                limiter.check().is_ok(),
-                "Request {} should succeed",
-                i
-            );
+            let status = layer.check_at("ip:10.0.0.1", limit, now).await;
+            assert!(status.allowed, "Request {} should succeed", i);
+            assert_eq!(status.remaining, 100 - i);
         }
 
         // 101st request should be rate limited
-        let result = limiter.check();
-        assert!(result.is_err(), "Request 101 should be rate limited");
+        let status = layer.check_at("ip:10.0.0.1", limit, now).await;
+        assert!(!status.allowed, "Request 101 should be rate limited");
+        assert_eq!(status.retry_after(now), 60 - now % 60);
     }
 
-    #[test]
-    fn test_rate_limiter_enforcement_authenticated() {
+    #[tokio::test]
+    async fn test_rate_limiter_enforcement_authenticated() {
         let layer = RateLimitLayer::new();
-        let limiter = layer.authenticated();
+        let user_id = Uuid::new_v4();
+        let key = user_client_key(user_id);
+        let limit = layer.limit_for(RateLimitTier::Authenticated, Some(user_id));
+        let now = 1_800_000_000;
 
         // Should allow up to 300 requests
         for i in 1..=300 {
             assert!(
-                limiter.check().is_ok(),
+                layer.check_at(&key, limit, now).await.allowed,
                 "Authenticated request {} should succeed",
                 i
             );
         }
 
         // 301st request should be rate limited
-        let result = limiter.check();
-        assert!(result.is_err(), "Authenticated request 301 should be rate limited");
+        assert!(
+            !layer.check_at(&key, limit, now).await.allowed,
+            "Authenticated request 301 should be rate limited"
+        );
+
+        // Other users have their own counter
+        let other = user_client_key(Uuid::new_v4());
+        assert!(layer.check_at(&other, limit, now).await.allowed);
     }
 
-    #[test]
-    fn test_bulk_operations_strict_limit() {
+    #[tokio::test]
+    async fn test_bulk_operations_strict_limit() {
         let layer = RateLimitLayer::new();
-        let bulk_limiter = layer.bulk();
+        let tier = RateLimitTier::for_request("/api/v1/settings/bulk", true);
+        assert_eq!(tier, RateLimitTier::Bulk);
+        assert_eq!(
+            RateLimitTier::for_request("/api/v1/cohorts/6f1c2a8e/notifications", true),
+            RateLimitTier::Bulk
+        );
+        assert_eq!(
+            RateLimitTier::for_request("/api/v1/cohorts/6f1c2a8e/preview", true),
+            RateLimitTier::Authenticated
+        );
+        assert_eq!(
+            RateLimitTier::for_request("/api/v1/recalls/rules/6f1c2a8e/run", true),
+            RateLimitTier::Bulk
+        );
+        assert_eq!(
+            RateLimitTier::for_request("/api/portal/v1/auth/login-link", false),
+            RateLimitTier::Bulk
+        );
+        assert_eq!(
+            RateLimitTier::for_request("/api/portal/v1/me", false),
+            RateLimitTier::Unauthenticated
+        );
+        assert_eq!(
+            RateLimitTier::for_request("/api/booking/v1/requests/verify", false),
+            RateLimitTier::Bulk
+        );
+        assert_eq!(
+            RateLimitTier::for_request("/api/booking/v1/slots", false),
+            RateLimitTier::Unauthenticated
+        );
+        assert_eq!(
+            RateLimitTier::for_request("/api/v1/appointment-links/respond", false),
+            RateLimitTier::Bulk
+        );
+        assert_eq!(
+            RateLimitTier::for_request("/api/v1/surveys/respond", false),
+            RateLimitTier::Bulk
+        );
+        assert_eq!(
+            RateLimitTier::for_request("/api/v1/televisit/join", false),
+            RateLimitTier::Bulk
+        );
+        // The doctor's own join is an ordinary request
+        assert_eq!(
+            RateLimitTier::for_request("/api/v1/appointments/abc/televisit/join", true),
+            RateLimitTier::Authenticated
+        );
+        assert_eq!(
+            RateLimitTier::for_request("/api/v1/waitlist-offers/respond", false),
+            RateLimitTier::Bulk
+        );
+
+        let key = tier.key("user:1");
+        let limit = layer.limit_for(tier, None);
+        let now = 1_800_000_000;
 
         // Bulk operations limited to 10/min
         for i in 1..=10 {
             assert!(
-                bulk_limiter.check().is_ok(),
+                layer.check_at(&key, limit, now).await.allowed,
                 "Bulk request {} should succeed",
                 i
             );
         }
 
-        // 11th request should be rate limited
-        assert!(bulk_limiter.check().is_err(), "Bulk request 11 should be rate limited");
+        // 11th request should be rate limited, other requests are not
+        assert!(!layer.check_at(&key, limit, now).await.allowed, "Bulk request 11 should be rate limited");
+        assert!(layer.check_at("user:1", 300, now).await.allowed);
+    }
+
+    /// Store that is always unreachable
+    struct FailingStore;
+
+    impl RateLimitStore for FailingStore {
+        fn name(&self) -> &'static str {
+            "failing"
+        }
+
+        fn hit<'a>(
+            &'a self,
+            _key: &'a str,
+            _window: u64,
+            _limit: u32,
+        ) -> futures::future::BoxFuture<'a, anyhow::Result<WindowHit>> {
+            Box::pin(async { Err(anyhow::anyhow!("connection refused")) })
+        }
+
+        fn usage<'a>(
+            &'a self,
+            _key: &'a str,
+            _window: u64,
+        ) -> futures::future::BoxFuture<'a, anyhow::Result<u32>> {
+            Box::pin(async { Err(anyhow::anyhow!("connection refused")) })
+        }
+
+        fn reset<'a>(
+            &'a self,
+            _key: &'a str,
+            _window: u64,
+        ) -> futures::future::BoxFuture<'a, anyhow::Result<()>> {
+            Box::pin(async { Err(anyhow::anyhow!("connection refused")) })
+        }
+
+        fn prune(&self, _window: u64) -> futures::future::BoxFuture<'_, anyhow::Result<()>> {
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rate_limiter_allows_requests_when_store_fails() {
+        let layer = RateLimitLayer::with_store(RateLimitConfig::default(), Arc::new(FailingStore));
+        let now = 1_800_000_000;
+
+        assert!(layer.check_at("ip:10.0.0.1", 1, now).await.allowed);
+        assert!(layer.check_at("ip:10.0.0.1", 1, now).await.allowed);
+        assert_eq!(layer.usage_at("ip:10.0.0.1", now).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_window_resets() {
+        let layer = RateLimitLayer::new();
+        let now = 1_800_000_000 - 1_800_000_000 % 60;
+
+        assert!(layer.check_at("ip:10.0.0.1", 1, now).await.allowed);
+        assert!(!layer.check_at("ip:10.0.0.1", 1, now + 59).await.allowed);
+        assert_eq!(layer.usage_at("ip:10.0.0.1", now + 59).await, 1);
+
+        // The next window starts from zero
+        assert!(layer.check_at("ip:10.0.0.1", 1, now + 60).await.allowed);
     }
 
     #[test]
-    fn test_independent_limiters() {
+    fn test_user_quota_overrides_limits() {
         let layer = RateLimitLayer::new();
-        let unauth = layer.unauthenticated();
-        let auth = layer.authenticated();
-        let bulk = layer.bulk();
+        let user_id = Uuid::new_v4();
+        layer.set_quota(
+            user_id,
+            Some(UserQuota {
+                requests_per_minute: 1000,
+                bulk_requests_per_minute: 50,
+            }),
+        );
 
-        // Exhaust unauthenticated limiter
-        for _ in 0..100 {
-            assert!(unauth.check().is_ok());
-        }
-        assert!(unauth.check().is_err());
+        assert_eq!(layer.limit_for(RateLimitTier::Authenticated, Some(user_id)), 1000);
+        assert_eq!(layer.limit_for(RateLimitTier::Bulk, Some(user_id)), 50);
+        assert_eq!(layer.limit_for(RateLimitTier::Authenticated, Some(Uuid::new_v4())), 300);
 
-        // Other limiters should still work
-        assert!(auth.check().is_ok(), "Authenticated limiter should be independent");
-        assert!(bulk.check().is_ok(), "Bulk limiter should be independent");
+        layer.set_quota(user_id, None);
+        assert_eq!(layer.limit_for(RateLimitTier::Authenticated, Some(user_id)), 300);
     }
 
     #[test]
     fn test_rate_limit_headers() {
         let mut headers = HeaderMap::new();
-        add_rate_limit_headers(&mut headers, 100, true);
+        let status = RateLimitStatus {
+            limit: 100,
+            remaining: 99,
+            reset_at: 1_800_000_060,
+            allowed: true,
+        };
+        add_rate_limit_headers(&mut headers, &status);
 
         // Verify headers are added
         assert!(headers.contains_key("X-RateLimit-Limit"));
@@ -364,12 +1064,20 @@ mod tests {
             headers.get("X-RateLimit-Limit").unwrap().to_str().unwrap(),
             "100"
         );
+        assert_eq!(
+            headers.get("X-RateLimit-Reset").unwrap().to_str().unwrap(),
+            "1800000060"
+        );
     }
 
-    #[test]
-    fn test_rate_limit_headers_no_quota() {
+    #[tokio::test]
+    async fn test_rate_limit_headers_no_quota() {
+        let layer = RateLimitLayer::new();
+        let now = 1_800_000_000;
+        layer.check_at("ip:10.0.0.1", 1, now).await;
+
         let mut headers = HeaderMap::new();
-        add_rate_limit_headers(&mut headers, 100, false);
+        add_rate_limit_headers(&mut headers, &layer.check_at("ip:10.0.0.1", 1, now).await);
 
         // Remaining should be 0 when no quota
         assert_eq!(
@@ -377,4 +1085,200 @@ mod tests {
             "0"
         );
     }
+
+    fn test_throttle() -> LoginThrottle {
+        LoginThrottle::with_config(LoginThrottleConfig {
+            window: Duration::from_secs(60),
+            max_failures_per_ip: 4,
+            max_failures_per_ip_username: 2,
+            ban_duration: Duration::from_secs(300),
+        })
+    }
+
+    /// Start of a failure window and of a ban period
+    const START: u64 = 1_800_000_000;
+
+    #[tokio::test]
+    async fn test_login_throttle_bans_ip_username_pair() {
+        let throttle = test_throttle();
+        let now = START;
+
+        assert!(throttle.record_failure_at("10.0.0.1", Some("doctor1"), now).await.is_empty());
+        assert!(throttle.record_failure_at("10.0.0.1", Some("doctor1"), now).await.is_empty());
+        assert_eq!(
+            throttle.record_failure_at("10.0.0.1", Some("Doctor1"), now).await,
+            vec![BanScope::IpUsername]
+        );
+
+        assert!(throttle.banned_for_at("10.0.0.1", Some("doctor1"), now).await.is_some());
+        // Other usernames and other IPs are unaffected
+        assert!(throttle.banned_for_at("10.0.0.1", Some("admin"), now).await.is_none());
+        assert!(throttle.banned_for_at("10.0.0.2", Some("doctor1"), now).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_login_throttle_bans_ip_rotating_usernames() {
+        let throttle = test_throttle();
+        let now = START;
+
+        for i in 0..4 {
+            let username = format!("user{}", i);
+            assert!(throttle.record_failure_at("10.0.0.1", Some(&username), now).await.is_empty());
+        }
+        assert_eq!(
+            throttle.record_failure_at("10.0.0.1", Some("user4"), now).await,
+            vec![BanScope::Ip]
+        );
+        assert!(throttle.banned_for_at("10.0.0.1", Some("anyone"), now).await.is_some());
+        assert!(throttle.banned_for_at("10.0.0.1", None, now).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_login_throttle_failures_slide_out_of_window() {
+        let throttle = test_throttle();
+
+        throttle.record_failure_at("10.0.0.1", Some("doctor1"), START).await;
+        throttle.record_failure_at("10.0.0.1", Some("doctor1"), START).await;
+
+        // Half a window later only half of the old failures still count
+        let later = START + 90;
+        assert!(throttle.record_failure_at("10.0.0.1", Some("doctor1"), later).await.is_empty());
+        assert!(throttle.banned_for_at("10.0.0.1", Some("doctor1"), later).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_login_throttle_window_slides_across_boundary() {
+        let throttle = test_throttle();
+
+        // Two failures at the end of one period...
+        for username in ["user0", "user1"] {
+            let now = START + 50;
+            assert!(throttle.record_failure_at("10.0.0.1", Some(username), now).await.is_empty());
+        }
+
+        // ...are still in the window early in the next: the fifth bans
+        let now = START + 70;
+        for username in ["user2", "user3"] {
+            assert!(throttle.record_failure_at("10.0.0.1", Some(username), now).await.is_empty());
+        }
+        assert_eq!(
+            throttle.record_failure_at("10.0.0.1", Some("user4"), now).await,
+            vec![BanScope::Ip]
+        );
+        assert!(throttle.banned_for_at("10.0.0.1", None, now).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_login_throttle_recent_failures() {
+        let throttle = test_throttle();
+
+        assert_eq!(throttle.recent_failures_at("10.0.0.1", "doctor1", START).await, 0);
+        throttle.record_failure_at("10.0.0.1", Some("doctor1"), START).await;
+        throttle.record_failure_at("10.0.0.1", Some("nurse1"), START).await;
+        assert_eq!(throttle.recent_failures_at("10.0.0.1", "admin", START).await, 2);
+        assert_eq!(throttle.recent_failures_at("10.0.0.2", "admin", START).await, 0);
+
+        // Failures leave the window as it slides past them
+        assert_eq!(throttle.recent_failures_at("10.0.0.1", "admin", START + 60).await, 2);
+        assert_eq!(throttle.recent_failures_at("10.0.0.1", "admin", START + 90).await, 1);
+        assert_eq!(throttle.recent_failures_at("10.0.0.1", "admin", START + 120).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_login_throttle_counts_username_across_ips() {
+        let throttle = test_throttle();
+
+        // One failure per IP never reaches a ban, but adds up per username
+        for i in 1..=5 {
+            let ip = format!("10.0.0.{}", i);
+            assert!(throttle.record_failure_at(&ip, Some("Doctor1"), START).await.is_empty());
+        }
+        assert_eq!(throttle.recent_failures_at("10.0.0.9", "doctor1", START).await, 5);
+        assert!(throttle.banned_for_at("10.0.0.9", Some("doctor1"), START).await.is_none());
+
+        throttle.record_success_at("10.0.0.9", "doctor1", START).await;
+        assert_eq!(throttle.recent_failures_at("10.0.0.9", "doctor1", START).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_login_throttle_ban_expires() {
+        let throttle = test_throttle();
+        let now = START + 100;
+
+        for _ in 0..3 {
+            throttle.record_failure_at("10.0.0.1", Some("doctor1"), now).await;
+        }
+
+        // Banned until the end of the next ban period
+        assert_eq!(
+            throttle.banned_for_at("10.0.0.1", Some("doctor1"), now).await,
+            Some(Duration::from_secs(500))
+        );
+        assert_eq!(
+            throttle.banned_for_at("10.0.0.1", Some("doctor1"), START + 400).await,
+            Some(Duration::from_secs(200))
+        );
+        assert!(throttle
+            .banned_for_at("10.0.0.1", Some("doctor1"), START + 600)
+            .await
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_login_throttle_bans_once_across_instances() {
+        let store: Arc<dyn RateLimitStore> = Arc::new(MemoryRateLimitStore::new());
+        let config = test_throttle().config().clone();
+        let first = LoginThrottle::with_store(config.clone(), store.clone());
+        let second = LoginThrottle::with_store(config, store);
+
+        // Failures on both instances add up, and the ban is reported once
+        assert!(first.record_failure_at("10.0.0.1", Some("doctor1"), START).await.is_empty());
+        assert!(second.record_failure_at("10.0.0.1", Some("doctor1"), START).await.is_empty());
+        assert_eq!(
+            first.record_failure_at("10.0.0.1", Some("doctor1"), START).await,
+            vec![BanScope::IpUsername]
+        );
+        assert!(second.banned_for_at("10.0.0.1", Some("doctor1"), START).await.is_some());
+        assert!(!second.ban("login:ipu:10.0.0.1:doctor1", START).await);
+    }
+
+    #[tokio::test]
+    async fn test_login_throttle_allows_logins_when_store_fails() {
+        let throttle =
+            LoginThrottle::with_store(LoginThrottleConfig::default(), Arc::new(FailingStore));
+
+        for _ in 0..30 {
+            assert!(throttle.record_failure_at("10.0.0.1", Some("doctor1"), START).await.is_empty());
+        }
+        assert!(throttle.banned_for_at("10.0.0.1", Some("doctor1"), START).await.is_none());
+        assert_eq!(throttle.recent_failures_at("10.0.0.1", "doctor1", START).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_login_throttle_success_resets_pair_only() {
+        let throttle = test_throttle();
+        let now = START;
+
+        throttle.record_failure_at("10.0.0.1", Some("doctor1"), now).await;
+        throttle.record_failure_at("10.0.0.1", Some("doctor1"), now).await;
+        throttle.record_success_at("10.0.0.1", "doctor1", now).await;
+        assert!(throttle.record_failure_at("10.0.0.1", Some("doctor1"), now).await.is_empty());
+
+        // The per-IP count kept growing: this is the fourth failure, the fifth bans
+        assert!(throttle.record_failure_at("10.0.0.1", Some("other"), now).await.is_empty());
+        assert_eq!(
+            throttle.record_failure_at("10.0.0.1", Some("third"), now).await,
+            vec![BanScope::Ip]
+        );
+    }
+
+    #[test]
+    fn test_login_username_extraction() {
+        assert_eq!(
+            login_username(br#"{"username":" Doctor1 ","password":"x"}"#),
+            Some("doctor1".to_string())
+        );
+        assert_eq!(login_username(br#"{"password":"x"}"#), None);
+        assert_eq!(login_username(b"not json"), None);
+    }
 }
@@ -0,0 +1,498 @@
+/*!
+ * Rate Limit Counter Stores
+ *
+ * Request counters of the rate limiter behind the `RateLimitStore` trait,
+ * selected with RATE_LIMIT_BACKEND:
+ * - `memory` (default): counters of this instance only; with several
+ *   replicas each enforces the limits on its own share of the traffic
+ * - `postgres`: counters in the `rate_limit_counters` table, shared by all
+ *   instances using the same database
+ * - `redis`: counters in Redis (REDIS_URL), shared by all instances; needs
+ *   the `redis-cache` feature
+ *
+ * Every store counts requests per key in fixed windows identified by their
+ * index (Unix time / window length). A request is only counted while the
+ * key is under its limit, so rejected requests do not extend a block.
+ * Counters of a future window are kept until that window has passed: the
+ * login throttle files the counter of each of its periods under the last
+ * minute of the next one, and slides its window over the two.
+ */
+
+use anyhow::{Context, Result};
+use futures::future::BoxFuture;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::middleware::rate_limit::RATE_LIMIT_WINDOW_SECS;
+
+/// Number of tracked keys above which counters of past windows are dropped
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// Outcome of counting a request
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WindowHit {
+    /// Requests counted in the window, this one included if allowed
+    pub count: u32,
+    pub allowed: bool,
+}
+
+/// Where the rate limiter keeps its request counters
+pub trait RateLimitStore: Send + Sync {
+    /// Store name, for logs
+    fn name(&self) -> &'static str;
+
+    /// Count a request against a key in a window, unless `limit` is reached
+    fn hit<'a>(&'a self, key: &'a str, window: u64, limit: u32)
+        -> BoxFuture<'a, Result<WindowHit>>;
+
+    /// Requests counted against a key in a window
+    fn usage<'a>(&'a self, key: &'a str, window: u64) -> BoxFuture<'a, Result<u32>>;
+
+    /// Drop the counter of a key in a window
+    fn reset<'a>(&'a self, key: &'a str, window: u64) -> BoxFuture<'a, Result<()>>;
+
+    /// Drop the counters of windows before `window`
+    fn prune(&self, window: u64) -> BoxFuture<'_, Result<()>>;
+}
+
+/// Counter backend selected with RATE_LIMIT_BACKEND
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RateLimitBackend {
+    Memory,
+    Postgres,
+    /// Redis connection URL
+    Redis(String),
+}
+
+impl RateLimitBackend {
+    /// Read RATE_LIMIT_BACKEND (`memory`, `postgres` or `redis`; `redis`
+    /// also needs REDIS_URL)
+    pub fn from_env() -> Result<Self> {
+        let backend = std::env::var("RATE_LIMIT_BACKEND").unwrap_or_default();
+        match backend.trim().to_lowercase().as_str() {
+            "" | "memory" => Ok(Self::Memory),
+            "postgres" => Ok(Self::Postgres),
+            "redis" => {
+                let url = std::env::var("REDIS_URL")
+                    .ok()
+                    .filter(|url| !url.trim().is_empty())
+                    .context("RATE_LIMIT_BACKEND=redis requires REDIS_URL")?;
+                Ok(Self::Redis(url))
+            }
+            other => anyhow::bail!(
+                "Invalid RATE_LIMIT_BACKEND '{}' (expected memory, postgres or redis)",
+                other
+            ),
+        }
+    }
+}
+
+/// Build the store of the configured backend
+pub async fn build_rate_limit_store(
+    backend: &RateLimitBackend,
+    pool: &PgPool,
+) -> Result<Arc<dyn RateLimitStore>> {
+    Ok(match backend {
+        RateLimitBackend::Memory => Arc::new(MemoryRateLimitStore::new()),
+        RateLimitBackend::Postgres => Arc::new(PostgresRateLimitStore::new(pool.clone())),
+        #[cfg(feature = "redis-cache")]
+        RateLimitBackend::Redis(url) => Arc::new(RedisRateLimitStore::connect(url).await?),
+        #[cfg(not(feature = "redis-cache"))]
+        RateLimitBackend::Redis(_) => {
+            anyhow::bail!("RATE_LIMIT_BACKEND=redis requires the redis-cache feature")
+        }
+    })
+}
+
+/// Index of the current window
+fn current_window() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+        / RATE_LIMIT_WINDOW_SECS
+}
+
+/// Requests counted in the current window of a key
+#[derive(Clone, Copy, Debug)]
+struct Window {
+    index: u64,
+    count: u32,
+}
+
+/// Counters of this instance only
+#[derive(Default)]
+pub struct MemoryRateLimitStore {
+    windows: Mutex<HashMap<String, Window>>,
+}
+
+impl MemoryRateLimitStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn hit_sync(&self, key: &str, index: u64, limit: u32) -> WindowHit {
+        let mut windows = self.windows.lock().unwrap();
+        if windows.len() > PRUNE_THRESHOLD {
+            // Counters filed under a later window are still live
+            let current = index.min(current_window());
+            windows.retain(|_, window| window.index >= current);
+        }
+
+        let window = windows
+            .entry(key.to_string())
+            .or_insert(Window { index, count: 0 });
+        if window.index != index {
+            *window = Window { index, count: 0 };
+        }
+
+        let allowed = window.count < limit;
+        if allowed {
+            window.count += 1;
+        }
+
+        WindowHit {
+            count: window.count,
+            allowed,
+        }
+    }
+
+    fn reset_sync(&self, key: &str, index: u64) {
+        let mut windows = self.windows.lock().unwrap();
+        if windows.get(key).is_some_and(|window| window.index == index) {
+            windows.remove(key);
+        }
+    }
+
+    fn usage_sync(&self, key: &str, index: u64) -> u32 {
+        self.windows
+            .lock()
+            .unwrap()
+            .get(key)
+            .filter(|window| window.index == index)
+            .map_or(0, |window| window.count)
+    }
+}
+
+impl RateLimitStore for MemoryRateLimitStore {
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+
+    fn hit<'a>(
+        &'a self,
+        key: &'a str,
+        window: u64,
+        limit: u32,
+    ) -> BoxFuture<'a, Result<WindowHit>> {
+        let hit = self.hit_sync(key, window, limit);
+        Box::pin(async move { Ok(hit) })
+    }
+
+    fn usage<'a>(&'a self, key: &'a str, window: u64) -> BoxFuture<'a, Result<u32>> {
+        let count = self.usage_sync(key, window);
+        Box::pin(async move { Ok(count) })
+    }
+
+    fn reset<'a>(&'a self, key: &'a str, window: u64) -> BoxFuture<'a, Result<()>> {
+        self.reset_sync(key, window);
+        Box::pin(async { Ok(()) })
+    }
+
+    fn prune(&self, window: u64) -> BoxFuture<'_, Result<()>> {
+        self.windows
+            .lock()
+            .unwrap()
+            .retain(|_, counter| counter.index >= window);
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// Counters in the `rate_limit_counters` table (UNLOGGED: losing them on a
+/// crash only resets the current window)
+pub struct PostgresRateLimitStore {
+    pool: PgPool,
+}
+
+impl PostgresRateLimitStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+impl RateLimitStore for PostgresRateLimitStore {
+    fn name(&self) -> &'static str {
+        "postgres"
+    }
+
+    fn hit<'a>(
+        &'a self,
+        key: &'a str,
+        window: u64,
+        limit: u32,
+    ) -> BoxFuture<'a, Result<WindowHit>> {
+        Box::pin(async move {
+            // No row returned: the key is at its limit and was not counted
+            let count: Option<i32> = sqlx::query_scalar(
+                r#"
+                INSERT INTO rate_limit_counters (key, window_index, count)
+                VALUES ($1, $2, 1)
+                ON CONFLICT (key, window_index) DO UPDATE
+                SET count = rate_limit_counters.count + 1
+                WHERE rate_limit_counters.count < $3
+                RETURNING count
+                "#,
+            )
+            .bind(key)
+            .bind(window as i64)
+            .bind(limit as i32)
+            .fetch_optional(&self.pool)
+            .await
+            .context("Failed to count request")?;
+
+            Ok(match count {
+                Some(count) => WindowHit {
+                    count: count.max(0) as u32,
+                    allowed: true,
+                },
+                None => WindowHit {
+                    count: limit,
+                    allowed: false,
+                },
+            })
+        })
+    }
+
+    fn usage<'a>(&'a self, key: &'a str, window: u64) -> BoxFuture<'a, Result<u32>> {
+        Box::pin(async move {
+            let count: Option<i32> = sqlx::query_scalar(
+                "SELECT count FROM rate_limit_counters WHERE key = $1 AND window_index = $2",
+            )
+            .bind(key)
+            .bind(window as i64)
+            .fetch_optional(&self.pool)
+            .await
+            .context("Failed to read request count")?;
+
+            Ok(count.unwrap_or(0).max(0) as u32)
+        })
+    }
+
+    fn reset<'a>(&'a self, key: &'a str, window: u64) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            sqlx::query("DELETE FROM rate_limit_counters WHERE key = $1 AND window_index = $2")
+                .bind(key)
+                .bind(window as i64)
+                .execute(&self.pool)
+                .await
+                .context("Failed to reset request count")?;
+            Ok(())
+        })
+    }
+
+    fn prune(&self, window: u64) -> BoxFuture<'_, Result<()>> {
+        Box::pin(async move {
+            sqlx::query("DELETE FROM rate_limit_counters WHERE window_index < $1")
+                .bind(window as i64)
+                .execute(&self.pool)
+                .await
+                .context("Failed to prune request counters")?;
+            Ok(())
+        })
+    }
+}
+
+/// Count a request unless the key is at its limit; returns the new count,
+/// or -1 when the limit is reached. The key expires at ARGV[2] (Unix time).
+#[cfg(feature = "redis-cache")]
+const REDIS_HIT_SCRIPT: &str = r#"
+local count = tonumber(redis.call('GET', KEYS[1]) or '0')
+if count >= tonumber(ARGV[1]) then
+    return -1
+end
+count = redis.call('INCR', KEYS[1])
+if count == 1 then
+    redis.call('EXPIREAT', KEYS[1], ARGV[2])
+end
+return count
+"#;
+
+/// Counters in Redis, one key per client and window
+#[cfg(feature = "redis-cache")]
+pub struct RedisRateLimitStore {
+    connection: redis::aio::ConnectionManager,
+    script: redis::Script,
+}
+
+#[cfg(feature = "redis-cache")]
+impl RedisRateLimitStore {
+    /// Connect to Redis
+    pub async fn connect(url: &str) -> Result<Self> {
+        let client = redis::Client::open(url).context("Invalid REDIS_URL")?;
+        let connection = redis::aio::ConnectionManager::new(client)
+            .await
+            .context("Failed to connect to Redis")?;
+
+        Ok(Self {
+            connection,
+            script: redis::Script::new(REDIS_HIT_SCRIPT),
+        })
+    }
+
+    fn window_key(key: &str, window: u64) -> String {
+        format!("docpat:ratelimit:{}:{}", window, key)
+    }
+}
+
+#[cfg(feature = "redis-cache")]
+impl RateLimitStore for RedisRateLimitStore {
+    fn name(&self) -> &'static str {
+        "redis"
+    }
+
+    fn hit<'a>(
+        &'a self,
+        key: &'a str,
+        window: u64,
+        limit: u32,
+    ) -> BoxFuture<'a, Result<WindowHit>> {
+        Box::pin(async move {
+            let mut connection = self.connection.clone();
+            // Keep the key until a window after its own, so clock skew
+            // between instances does not reset a window early
+            let expire_at = (window + 2) * RATE_LIMIT_WINDOW_SECS;
+            let count: i64 = self
+                .script
+                .key(Self::window_key(key, window))
+                .arg(limit)
+                .arg(expire_at)
+                .invoke_async(&mut connection)
+                .await
+                .context("Failed to count request in Redis")?;
+
+            Ok(if count < 0 {
+                WindowHit {
+                    count: limit,
+                    allowed: false,
+                }
+            } else {
+                WindowHit {
+                    count: count as u32,
+                    allowed: true,
+                }
+            })
+        })
+    }
+
+    fn usage<'a>(&'a self, key: &'a str, window: u64) -> BoxFuture<'a, Result<u32>> {
+        Box::pin(async move {
+            let mut connection = self.connection.clone();
+            let count: Option<u32> = redis::cmd("GET")
+                .arg(Self::window_key(key, window))
+                .query_async(&mut connection)
+                .await
+                .context("Failed to read request count from Redis")?;
+
+            Ok(count.unwrap_or(0))
+        })
+    }
+
+    fn reset<'a>(&'a self, key: &'a str, window: u64) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let mut connection = self.connection.clone();
+            let _: () = redis::cmd("DEL")
+                .arg(Self::window_key(key, window))
+                .query_async(&mut connection)
+                .await
+                .context("Failed to reset request count in Redis")?;
+            Ok(())
+        })
+    }
+
+    fn prune(&self, _window: u64) -> BoxFuture<'_, Result<()>> {
+        // Keys expire on their own
+        Box::pin(async { Ok(()) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_memory_store_counts_per_window() {
+        let store = MemoryRateLimitStore::new();
+
+        assert_eq!(
+            store.hit("ip:10.0.0.1", 7, 2).await.unwrap(),
+            WindowHit {
+                count: 1,
+                allowed: true
+            }
+        );
+        assert!(store.hit("ip:10.0.0.1", 7, 2).await.unwrap().allowed);
+
+        // At the limit: not counted
+        assert_eq!(
+            store.hit("ip:10.0.0.1", 7, 2).await.unwrap(),
+            WindowHit {
+                count: 2,
+                allowed: false
+            }
+        );
+        assert_eq!(store.usage("ip:10.0.0.1", 7).await.unwrap(), 2);
+
+        // The next window starts from zero
+        assert!(store.hit("ip:10.0.0.1", 8, 2).await.unwrap().allowed);
+        assert_eq!(store.usage("ip:10.0.0.1", 7).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_prune() {
+        let store = MemoryRateLimitStore::new();
+        store.hit("ip:10.0.0.1", 7, 5).await.unwrap();
+        store.hit("ip:10.0.0.2", 8, 5).await.unwrap();
+
+        store.prune(8).await.unwrap();
+
+        let windows = store.windows.lock().unwrap();
+        assert!(!windows.contains_key("ip:10.0.0.1"));
+        assert!(windows.contains_key("ip:10.0.0.2"));
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_reset() {
+        let store = MemoryRateLimitStore::new();
+        store.hit("login:ip:10.0.0.1", 7, 5).await.unwrap();
+        store.hit("login:ip:10.0.0.1", 7, 5).await.unwrap();
+
+        // Another window is left alone
+        store.reset("login:ip:10.0.0.1", 6).await.unwrap();
+        assert_eq!(store.usage("login:ip:10.0.0.1", 7).await.unwrap(), 2);
+
+        store.reset("login:ip:10.0.0.1", 7).await.unwrap();
+        assert_eq!(store.usage("login:ip:10.0.0.1", 7).await.unwrap(), 0);
+    }
+
+    #[test]
+    fn test_rate_limit_backend_from_env() {
+        std::env::remove_var("RATE_LIMIT_BACKEND");
+        assert_eq!(
+            RateLimitBackend::from_env().unwrap(),
+            RateLimitBackend::Memory
+        );
+
+        std::env::set_var("RATE_LIMIT_BACKEND", "Postgres");
+        assert_eq!(
+            RateLimitBackend::from_env().unwrap(),
+            RateLimitBackend::Postgres
+        );
+
+        std::env::set_var("RATE_LIMIT_BACKEND", "memcached");
+        assert!(RateLimitBackend::from_env().is_err());
+
+        std::env::remove_var("RATE_LIMIT_BACKEND");
+    }
+}
@@ -3,55 +3,50 @@
  *
  * Extracts HTTP request metadata (IP address, user agent, request ID) and
  * makes it available to handlers via request extensions.
+ *
+ * The request ID is the end-to-end correlation ID: an inbound `X-Request-Id`
+ * (UUID) is honoured, the ID is returned in the `X-Request-Id` response header
+ * and added to JSON error bodies so support tickets can be traced to audit
+ * entries, notification rows and background job logs.
  */
 
 use axum::{
-    body::Body,
+    body::{to_bytes, Body},
     extract::ConnectInfo,
-    http::Request,
+    http::{header, HeaderValue, Request},
     middleware::Next,
     response::Response,
 };
 use std::net::SocketAddr;
+use tracing::Instrument;
+use uuid::Uuid;
 
+use crate::middleware::client_ip::client_ip;
+use crate::models::request_context::{parse_request_id, with_request_id_scope, REQUEST_ID_HEADER};
 use crate::models::RequestContext;
 
+/// Maximum error body size rewritten to include the request ID
+const MAX_ERROR_BODY_BYTES: usize = 64 * 1024;
+
 /// Middleware that extracts request context (IP, user agent, request ID)
 /// and inserts it into request extensions.
 ///
 /// This should be applied after authentication middleware so that the
 /// authenticated user context is available, but before handlers execute.
 ///
-/// # IP Address Extraction Priority
-/// 1. X-Forwarded-For header (first IP if comma-separated)
-/// 2. X-Real-IP header
-/// 3. Direct connection IP (ConnectInfo)
+/// The IP address is the connection peer, or the rightmost forwarded hop
+/// that is not a trusted proxy (see [`client_ip`]).
 pub async fn request_context_middleware(
     mut request: Request<Body>,
     next: Next,
 ) -> Response {
-    // Try to get ConnectInfo from extensions (added by axum's into_make_service_with_connect_info)
-    let connect_info = request.extensions().get::<ConnectInfo<SocketAddr>>().cloned();
-
-    // Extract IP address from headers or connection info
-    // Priority: X-Forwarded-For > X-Real-IP > Direct connection
-    let ip_address = request
-        .headers()
-        .get("x-forwarded-for")
-        .and_then(|v| v.to_str().ok())
-        .and_then(|s| s.split(',').next())
-        .map(|s| s.trim().to_string())
-        .or_else(|| {
-            request
-                .headers()
-                .get("x-real-ip")
-                .and_then(|v| v.to_str().ok())
-                .map(|s| s.to_string())
-        })
-        .or_else(|| {
-            // Fallback to direct connection IP (for development without reverse proxy)
-            connect_info.map(|ConnectInfo(addr)| addr.ip().to_string())
-        });
+    // Resolve the client IP from the connection peer, following forwarding
+    // headers only through trusted proxies
+    let ip_address = client_ip(
+        request.headers(),
+        request.extensions().get::<ConnectInfo<SocketAddr>>(),
+    )
+    .map(|ip| ip.to_string());
 
     // Extract User-Agent header
     let user_agent = request
@@ -60,8 +55,17 @@ pub async fn request_context_middleware(
         .and_then(|v| v.to_str().ok())
         .map(|s| s.to_string());
 
+    // Reuse the caller's correlation ID when it is a valid UUID
+    let request_id = parse_request_id(
+        request
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|v| v.to_str().ok()),
+    )
+    .unwrap_or_else(Uuid::new_v4);
+
     // Create request context and insert into extensions
-    let ctx = RequestContext::new(ip_address, user_agent);
+    let ctx = RequestContext::with_request_id(ip_address, user_agent, request_id);
 
     tracing::debug!(
         request_id = %ctx.request_id,
@@ -71,6 +75,120 @@ pub async fn request_context_middleware(
 
     request.extensions_mut().insert(ctx);
 
-    // Continue processing the request
-    next.run(request).await
+    // Continue processing the request with the correlation ID in scope
+    let span = tracing::info_span!("request", request_id = %request_id);
+    let response = with_request_id_scope(request_id, next.run(request))
+        .instrument(span)
+        .await;
+
+    attach_request_id(response, request_id).await
+}
+
+/// Add the correlation ID to the response header and to JSON error bodies
+async fn attach_request_id(response: Response, request_id: Uuid) -> Response {
+    let (mut parts, body) = response.into_parts();
+
+    if let Ok(value) = HeaderValue::from_str(&request_id.to_string()) {
+        parts.headers.insert(REQUEST_ID_HEADER, value);
+    }
+
+    let is_json_error = (parts.status.is_client_error() || parts.status.is_server_error())
+        && parts
+            .headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(|ct| ct.contains("application/json"))
+            .unwrap_or(false);
+
+    if !is_json_error {
+        return Response::from_parts(parts, body);
+    }
+
+    let bytes = match to_bytes(body, MAX_ERROR_BODY_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::warn!(request_id = %request_id, "Failed to buffer error response: {}", e);
+            parts.headers.remove(header::CONTENT_LENGTH);
+            return Response::from_parts(parts, Body::empty());
+        }
+    };
+
+    let body = match inject_request_id(&bytes, request_id) {
+        Some(rewritten) => {
+            parts.headers.remove(header::CONTENT_LENGTH);
+            Body::from(rewritten)
+        }
+        None => Body::from(bytes),
+    };
+
+    Response::from_parts(parts, body)
+}
+
+/// Insert `request_id` into a JSON object body (None if the body is not an object)
+fn inject_request_id(body: &[u8], request_id: Uuid) -> Option<Vec<u8>> {
+    let mut value: serde_json::Value = serde_json::from_slice(body).ok()?;
+    let object = value.as_object_mut()?;
+    object
+        .entry("request_id")
+        .or_insert_with(|| serde_json::Value::String(request_id.to_string()));
+    serde_json::to_vec(&value).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+    use axum::response::IntoResponse;
+
+    #[test]
+    fn test_inject_request_id_into_error_object() {
+        let id = Uuid::new_v4();
+        let body = br#"{"error":"NOT_FOUND","message":"Patient not found"}"#;
+
+        let rewritten = inject_request_id(body, id).unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&rewritten).unwrap();
+
+        assert_eq!(value["error"], "NOT_FOUND");
+        assert_eq!(value["request_id"], id.to_string());
+    }
+
+    #[test]
+    fn test_inject_request_id_ignores_non_objects() {
+        let id = Uuid::new_v4();
+        assert!(inject_request_id(b"[1,2,3]", id).is_none());
+        assert!(inject_request_id(b"not json", id).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_attach_request_id_to_error_response() {
+        let id = Uuid::new_v4();
+        let response = (
+            StatusCode::BAD_REQUEST,
+            axum::Json(serde_json::json!({ "error": "BAD_REQUEST" })),
+        )
+            .into_response();
+
+        let response = attach_request_id(response, id).await;
+        assert_eq!(
+            response.headers().get(REQUEST_ID_HEADER).unwrap(),
+            &id.to_string()
+        );
+
+        let bytes = to_bytes(response.into_body(), MAX_ERROR_BODY_BYTES).await.unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(value["request_id"], id.to_string());
+    }
+
+    #[tokio::test]
+    async fn test_attach_request_id_leaves_success_body_untouched() {
+        let id = Uuid::new_v4();
+        let response = axum::Json(serde_json::json!({ "ok": true })).into_response();
+
+        let response = attach_request_id(response, id).await;
+        assert!(response.headers().contains_key(REQUEST_ID_HEADER));
+
+        let bytes = to_bytes(response.into_body(), MAX_ERROR_BODY_BYTES).await.unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert!(value.get("request_id").is_none());
+    }
 }
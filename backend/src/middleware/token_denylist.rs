@@ -0,0 +1,207 @@
+/*!
+ * Token Denylist
+ *
+ * Server-side list of revoked token IDs (`jti`). Checked by the JWT
+ * authentication middleware so that a specific access token can be killed
+ * before it expires. Entries are persisted in `revoked_tokens` and reloaded at
+ * startup; they are dropped once the underlying token would have expired anyway.
+ *
+ * Each instance keeps its own copy of the list, so revocations made on other
+ * replicas are picked up by reloading `revoked_tokens` every
+ * `DENYLIST_REFRESH_SECS` seconds (`spawn_token_denylist_refresh`).
+ */
+
+use chrono::{DateTime, TimeZone, Utc};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use tokio::time::{sleep, Duration as TokioDuration};
+use uuid::Uuid;
+
+/// Seconds between reloads of revocations made by other instances
+pub const DENYLIST_REFRESH_SECS: u64 = 15;
+
+/// A token revocation to persist
+#[derive(Debug, Clone)]
+pub struct RevokedToken {
+    pub jti: String,
+    pub user_id: Uuid,
+    pub token_type: String,
+    pub expires_at: DateTime<Utc>,
+    pub revoked_by: Uuid,
+    pub reason: Option<String>,
+}
+
+/// Revoked token tracker
+#[derive(Clone, Default)]
+pub struct TokenDenylist {
+    /// Map of jti to the token's own expiry
+    entries: Arc<RwLock<HashMap<String, DateTime<Utc>>>>,
+}
+
+impl TokenDenylist {
+    /// Create an empty denylist
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load unexpired revocations from the database
+    ///
+    /// Expired rows are deleted first since they can no longer match a valid token.
+    pub async fn load(pool: &PgPool) -> Result<Self, sqlx::Error> {
+        sqlx::query("DELETE FROM revoked_tokens WHERE expires_at <= NOW()")
+            .execute(pool)
+            .await?;
+
+        let rows: Vec<(String, DateTime<Utc>)> =
+            sqlx::query_as("SELECT jti, expires_at FROM revoked_tokens")
+                .fetch_all(pool)
+                .await?;
+
+        let denylist = Self::new();
+        {
+            let mut entries = denylist.entries.write().unwrap();
+            entries.extend(rows);
+        }
+
+        Ok(denylist)
+    }
+
+    /// Add the unexpired revocations in the database to the in-memory list
+    ///
+    /// Picks up tokens revoked through other instances. Entries are only ever
+    /// added (a revocation cannot be undone), so a token revoked here while
+    /// the query runs is not lost. Returns the number of tracked tokens.
+    pub async fn refresh(&self, pool: &PgPool) -> Result<usize, sqlx::Error> {
+        let rows: Vec<(String, DateTime<Utc>)> =
+            sqlx::query_as("SELECT jti, expires_at FROM revoked_tokens WHERE expires_at > NOW()")
+                .fetch_all(pool)
+                .await?;
+
+        {
+            let mut entries = self.entries.write().unwrap();
+            entries.extend(rows);
+        }
+        self.cleanup_expired();
+
+        Ok(self.len())
+    }
+
+    /// Check whether a token ID has been revoked
+    pub fn is_revoked(&self, jti: &str) -> bool {
+        let entries = self.entries.read().unwrap();
+        entries.contains_key(jti)
+    }
+
+    /// Add a token ID to the in-memory list
+    pub fn insert(&self, jti: &str, expires_at: DateTime<Utc>) {
+        let mut entries = self.entries.write().unwrap();
+        entries.insert(jti.to_string(), expires_at);
+    }
+
+    /// Persist a revocation and add it to the in-memory list
+    ///
+    /// Revoking the same token twice is a no-op.
+    pub async fn revoke(&self, pool: &PgPool, token: RevokedToken) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO revoked_tokens (jti, user_id, token_type, expires_at, revoked_by, reason)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (jti) DO NOTHING
+            "#,
+        )
+        .bind(&token.jti)
+        .bind(token.user_id)
+        .bind(&token.token_type)
+        .bind(token.expires_at)
+        .bind(token.revoked_by)
+        .bind(&token.reason)
+        .execute(pool)
+        .await?;
+
+        self.insert(&token.jti, token.expires_at);
+        self.cleanup_expired();
+
+        Ok(())
+    }
+
+    /// Remove entries whose tokens have expired
+    pub fn cleanup_expired(&self) {
+        let mut entries = self.entries.write().unwrap();
+        let now = Utc::now();
+        entries.retain(|_, expires_at| *expires_at > now);
+    }
+
+    /// Get count of revoked tokens still tracked
+    pub fn len(&self) -> usize {
+        let entries = self.entries.read().unwrap();
+        entries.len()
+    }
+
+    /// Whether no revoked tokens are tracked
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Spawn the periodic reload of revocations made by other instances
+pub fn spawn_token_denylist_refresh(pool: PgPool, denylist: TokenDenylist) {
+    tokio::spawn(async move {
+        loop {
+            sleep(TokioDuration::from_secs(DENYLIST_REFRESH_SECS)).await;
+
+            if let Err(e) = denylist.refresh(&pool).await {
+                tracing::warn!("Failed to reload revoked tokens: {}", e);
+            }
+        }
+    });
+}
+
+/// Convert a JWT `exp` claim to a timestamp
+pub fn expiry_from_claim(exp: i64) -> DateTime<Utc> {
+    Utc.timestamp_opt(exp, 0).single().unwrap_or_else(Utc::now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_insert_and_check() {
+        let denylist = TokenDenylist::new();
+        assert!(!denylist.is_revoked("abc"));
+
+        denylist.insert("abc", Utc::now() + Duration::minutes(30));
+        assert!(denylist.is_revoked("abc"));
+        assert!(!denylist.is_revoked("def"));
+    }
+
+    #[test]
+    fn test_cleanup_expired() {
+        let denylist = TokenDenylist::new();
+        denylist.insert("expired", Utc::now() - Duration::seconds(1));
+        denylist.insert("live", Utc::now() + Duration::minutes(30));
+        assert_eq!(denylist.len(), 2);
+
+        denylist.cleanup_expired();
+        assert_eq!(denylist.len(), 1);
+        assert!(denylist.is_revoked("live"));
+        assert!(!denylist.is_revoked("expired"));
+    }
+
+    #[test]
+    fn test_clones_share_state() {
+        let denylist = TokenDenylist::new();
+        let clone = denylist.clone();
+
+        clone.insert("shared", Utc::now() + Duration::minutes(5));
+        assert!(denylist.is_revoked("shared"));
+    }
+
+    #[test]
+    fn test_expiry_from_claim() {
+        let exp = 1_700_000_000;
+        assert_eq!(expiry_from_claim(exp).timestamp(), exp);
+    }
+}
@@ -0,0 +1,268 @@
+/*!
+ * Access Delegation Model
+ *
+ * Time-limited access granted by a doctor (the grantor) to another doctor,
+ * typically a locum covering for them. A delegation covers a set of patients
+ * and/or individual appointments and is only honored inside its
+ * [starts_at, ends_at) window; it expires on its own when the window closes.
+ *
+ * Enforcement happens in PostgreSQL RLS (see the access_delegations migration)
+ * and in `middleware::authorization::delegation_window_middleware`, which
+ * turns away locum accounts that have no active delegation.
+ */
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// Longest allowed delegation window
+pub const MAX_DELEGATION_DAYS: i64 = 90;
+
+/// Maximum number of patients plus appointments in one delegation
+pub const MAX_DELEGATION_SCOPE: usize = 500;
+
+/// Delegation row with its scope
+#[derive(Debug, Clone, FromRow)]
+pub struct AccessDelegation {
+    pub id: Uuid,
+    pub grantor_id: Uuid,
+    pub delegate_id: Uuid,
+    pub reason: Option<String>,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub revoked_by: Option<Uuid>,
+    pub patient_ids: Vec<Uuid>,
+    pub appointment_ids: Vec<Uuid>,
+}
+
+/// Delegation status, derived from the window and revocation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum DelegationStatus {
+    /// Window has not started yet
+    Scheduled,
+    /// Inside the window
+    Active,
+    /// Window has closed
+    Expired,
+    /// Revoked before the window closed
+    Revoked,
+}
+
+impl AccessDelegation {
+    /// Status at `now`
+    pub fn status(&self, now: DateTime<Utc>) -> DelegationStatus {
+        if self.revoked_at.is_some() {
+            DelegationStatus::Revoked
+        } else if now < self.starts_at {
+            DelegationStatus::Scheduled
+        } else if now < self.ends_at {
+            DelegationStatus::Active
+        } else {
+            DelegationStatus::Expired
+        }
+    }
+
+    /// Whether the user granted or received this delegation
+    pub fn involves(&self, user_id: Uuid) -> bool {
+        self.grantor_id == user_id || self.delegate_id == user_id
+    }
+}
+
+/// Request body for granting a delegation
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateDelegationRequest {
+    /// Doctor whose patients/appointments are delegated
+    pub grantor_id: Uuid,
+    /// Doctor (usually a locum) receiving access
+    pub delegate_id: Uuid,
+    /// Defaults to now
+    pub starts_at: Option<DateTime<Utc>>,
+    pub ends_at: DateTime<Utc>,
+    pub reason: Option<String>,
+    #[serde(default)]
+    pub patient_ids: Vec<Uuid>,
+    #[serde(default)]
+    pub appointment_ids: Vec<Uuid>,
+}
+
+impl CreateDelegationRequest {
+    /// Start of the window, defaulting to `now`
+    pub fn starts_at_or(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        self.starts_at.unwrap_or(now)
+    }
+
+    /// Validate the window and scope
+    ///
+    /// # Errors
+    ///
+    /// Returns a message describing the first problem found
+    pub fn validate(&self, now: DateTime<Utc>) -> Result<(), String> {
+        if self.grantor_id == self.delegate_id {
+            return Err("A doctor cannot delegate access to themselves".to_string());
+        }
+
+        let starts_at = self.starts_at_or(now);
+        if self.ends_at <= starts_at {
+            return Err("ends_at must be after starts_at".to_string());
+        }
+        if self.ends_at <= now {
+            return Err("ends_at must be in the future".to_string());
+        }
+        if self.ends_at - starts_at > Duration::days(MAX_DELEGATION_DAYS) {
+            return Err(format!(
+                "Delegation window cannot exceed {} days",
+                MAX_DELEGATION_DAYS
+            ));
+        }
+
+        let scope = self.patient_ids.len() + self.appointment_ids.len();
+        if scope == 0 {
+            return Err("At least one patient or appointment must be delegated".to_string());
+        }
+        if scope > MAX_DELEGATION_SCOPE {
+            return Err(format!(
+                "A delegation can cover at most {} patients and appointments",
+                MAX_DELEGATION_SCOPE
+            ));
+        }
+
+        if self.reason.as_deref().is_some_and(|r| r.len() > 1000) {
+            return Err("reason cannot exceed 1000 characters".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+/// Query parameters for listing delegations
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DelegationFilter {
+    pub grantor_id: Option<Uuid>,
+    pub delegate_id: Option<Uuid>,
+    /// Include expired and revoked delegations
+    #[serde(default)]
+    pub include_inactive: bool,
+}
+
+/// Request body for marking a user as a locum
+#[derive(Debug, Clone, Deserialize)]
+pub struct SetLocumRequest {
+    pub is_locum: bool,
+}
+
+/// Delegation as returned by the API
+#[derive(Debug, Clone, Serialize)]
+pub struct DelegationResponse {
+    pub id: Uuid,
+    pub grantor_id: Uuid,
+    pub delegate_id: Uuid,
+    pub reason: Option<String>,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    pub status: DelegationStatus,
+    pub patient_ids: Vec<Uuid>,
+    pub appointment_ids: Vec<Uuid>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub revoked_by: Option<Uuid>,
+}
+
+impl DelegationResponse {
+    pub fn from_delegation(delegation: AccessDelegation, now: DateTime<Utc>) -> Self {
+        Self {
+            status: delegation.status(now),
+            id: delegation.id,
+            grantor_id: delegation.grantor_id,
+            delegate_id: delegation.delegate_id,
+            reason: delegation.reason,
+            starts_at: delegation.starts_at,
+            ends_at: delegation.ends_at,
+            patient_ids: delegation.patient_ids,
+            appointment_ids: delegation.appointment_ids,
+            created_by: delegation.created_by,
+            created_at: delegation.created_at,
+            revoked_at: delegation.revoked_at,
+            revoked_by: delegation.revoked_by,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn delegation(starts_at: DateTime<Utc>, ends_at: DateTime<Utc>) -> AccessDelegation {
+        AccessDelegation {
+            id: Uuid::new_v4(),
+            grantor_id: Uuid::new_v4(),
+            delegate_id: Uuid::new_v4(),
+            reason: None,
+            starts_at,
+            ends_at,
+            created_by: None,
+            created_at: starts_at,
+            revoked_at: None,
+            revoked_by: None,
+            patient_ids: vec![Uuid::new_v4()],
+            appointment_ids: vec![],
+        }
+    }
+
+    fn request(now: DateTime<Utc>) -> CreateDelegationRequest {
+        CreateDelegationRequest {
+            grantor_id: Uuid::new_v4(),
+            delegate_id: Uuid::new_v4(),
+            starts_at: None,
+            ends_at: now + Duration::days(7),
+            reason: Some("Summer cover".to_string()),
+            patient_ids: vec![Uuid::new_v4()],
+            appointment_ids: vec![],
+        }
+    }
+
+    #[test]
+    fn test_status_follows_window() {
+        let now = Utc::now();
+        let d = delegation(now - Duration::days(1), now + Duration::days(1));
+        assert_eq!(d.status(now), DelegationStatus::Active);
+        assert_eq!(d.status(now - Duration::days(2)), DelegationStatus::Scheduled);
+        assert_eq!(d.status(now + Duration::days(1)), DelegationStatus::Expired);
+
+        let revoked = AccessDelegation {
+            revoked_at: Some(now),
+            ..d
+        };
+        assert_eq!(revoked.status(now), DelegationStatus::Revoked);
+    }
+
+    #[test]
+    fn test_validate_request() {
+        let now = Utc::now();
+        assert!(request(now).validate(now).is_ok());
+
+        let mut same_user = request(now);
+        same_user.delegate_id = same_user.grantor_id;
+        assert!(same_user.validate(now).is_err());
+
+        let mut past = request(now);
+        past.starts_at = Some(now - Duration::days(3));
+        past.ends_at = now - Duration::days(1);
+        assert!(past.validate(now).is_err());
+
+        let mut too_long = request(now);
+        too_long.ends_at = now + Duration::days(MAX_DELEGATION_DAYS + 1);
+        assert!(too_long.validate(now).is_err());
+
+        let mut empty = request(now);
+        empty.patient_ids.clear();
+        assert!(empty.validate(now).is_err());
+        empty.appointment_ids.push(Uuid::new_v4());
+        assert!(empty.validate(now).is_ok());
+    }
+}
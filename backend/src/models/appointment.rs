@@ -5,26 +5,41 @@
  * Includes conflict detection, status workflow, and recurring appointment support.
  *
  * Status Workflow:
- * - SCHEDULED → CONFIRMED → IN_PROGRESS → COMPLETED
- * - SCHEDULED/CONFIRMED can go to CANCELLED or NO_SHOW
+ * - PENDING (booked online) → SCHEDULED or CONFIRMED once staff accept it
+ * - SCHEDULED → CONFIRMED → ARRIVED (checked in) → IN_PROGRESS → COMPLETED
+ * - PENDING can go to CANCELLED (request declined)
+ * - SCHEDULED/CONFIRMED/ARRIVED can go to CANCELLED or NO_SHOW
  * - COMPLETED, CANCELLED, and NO_SHOW are final states
  */
 
-use chrono::{DateTime, Utc};
+use crate::utils::encryption::EncryptionKey;
+use anyhow::Context;
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::Type;
+use std::collections::HashMap;
 use uuid::Uuid;
 use validator::Validate;
 
+/// Setting holding the buffer minutes per appointment type
+pub const APPOINTMENT_BUFFERS_SETTING: &str = "appointment.type_buffers";
+
+/// Longest buffer before or after an appointment, in minutes
+pub const MAX_BUFFER_MINUTES: i64 = 120;
+
 /// Appointment status enum representing the lifecycle of an appointment
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Type)]
 #[sqlx(type_name = "varchar", rename_all = "SCREAMING_SNAKE_CASE")]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum AppointmentStatus {
+    /// Booked online by the patient, waiting for staff to accept it
+    Pending,
     /// Appointment has been scheduled but not yet confirmed
     Scheduled,
     /// Appointment has been confirmed by patient or staff
     Confirmed,
+    /// Patient has arrived and is in the waiting room
+    Arrived,
     /// Appointment is currently in progress
     InProgress,
     /// Appointment has been completed
@@ -39,16 +54,33 @@ impl AppointmentStatus {
     /// Check if transition from current status to new status is valid
     pub fn can_transition_to(&self, new_status: &AppointmentStatus) -> bool {
         match self {
+            AppointmentStatus::Pending => matches!(
+                new_status,
+                AppointmentStatus::Pending
+                    | AppointmentStatus::Scheduled
+                    | AppointmentStatus::Confirmed
+                    | AppointmentStatus::Cancelled
+            ),
             AppointmentStatus::Scheduled => matches!(
                 new_status,
                 AppointmentStatus::Scheduled
                     | AppointmentStatus::Confirmed
+                    | AppointmentStatus::Arrived
                     | AppointmentStatus::Cancelled
                     | AppointmentStatus::NoShow
             ),
             AppointmentStatus::Confirmed => matches!(
                 new_status,
                 AppointmentStatus::Confirmed
+                    | AppointmentStatus::Arrived
+                    | AppointmentStatus::InProgress
+                    | AppointmentStatus::Cancelled
+                    | AppointmentStatus::NoShow
+            ),
+            // NO_SHOW when the patient leaves before being seen
+            AppointmentStatus::Arrived => matches!(
+                new_status,
+                AppointmentStatus::Arrived
                     | AppointmentStatus::InProgress
                     | AppointmentStatus::Cancelled
                     | AppointmentStatus::NoShow
@@ -78,7 +110,7 @@ impl AppointmentStatus {
 }
 
 /// Appointment type enum
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Type)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, Type)]
 #[sqlx(type_name = "varchar", rename_all = "SCREAMING_SNAKE_CASE")]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum AppointmentType {
@@ -94,6 +126,8 @@ pub enum AppointmentType {
     RoutineCheckup,
     /// Acupuncture session
     Acupuncture,
+    /// Video visit; creating one provisions a video room
+    Televisit,
 }
 
 impl AppointmentType {
@@ -106,6 +140,7 @@ impl AppointmentType {
             AppointmentType::Consultation => 45,
             AppointmentType::RoutineCheckup => 30,
             AppointmentType::Acupuncture => 45,
+            AppointmentType::Televisit => 30,
         }
     }
 }
@@ -158,14 +193,14 @@ pub struct Appointment {
     #[serde(rename = "type")]
     #[sqlx(rename = "type")]
     pub appointment_type: AppointmentType,
-    pub reason: Option<String>,
-    pub notes: Option<String>,
+    pub reason: Option<String>, // 🔒 Encrypted
+    pub notes: Option<String>,  // 🔒 Encrypted
 
     // Status
     pub status: AppointmentStatus,
 
     // Cancellation
-    pub cancellation_reason: Option<String>,
+    pub cancellation_reason: Option<String>, // 🔒 Encrypted
     pub cancelled_at: Option<DateTime<Utc>>,
     pub cancelled_by: Option<Uuid>,
 
@@ -186,8 +221,13 @@ pub struct Appointment {
 
     // Check-in/out
     pub checked_in_at: Option<DateTime<Utc>>,
+    pub visit_started_at: Option<DateTime<Utc>>,
     pub checked_out_at: Option<DateTime<Utc>>,
 
+    /// Whether reason, notes and cancellation_reason are encrypted; false
+    /// for rows saved before they were and not re-saved since
+    pub text_encrypted: bool,
+
     // Audit
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
@@ -272,40 +312,59 @@ pub struct AppointmentDto {
     pub reminder_sent_whatsapp: bool,
 
     pub checked_in_at: Option<DateTime<Utc>>,
+    pub visit_started_at: Option<DateTime<Utc>>,
     pub checked_out_at: Option<DateTime<Utc>>,
 
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
-impl From<Appointment> for AppointmentDto {
-    fn from(appointment: Appointment) -> Self {
-        AppointmentDto {
-            id: appointment.id,
-            patient_id: appointment.patient_id,
-            provider_id: appointment.provider_id,
-            scheduled_start: appointment.scheduled_start,
-            scheduled_end: appointment.scheduled_end,
-            duration_minutes: appointment.duration_minutes,
-            appointment_type: appointment.appointment_type,
-            reason: appointment.reason,
-            notes: appointment.notes,
-            status: appointment.status,
-            cancellation_reason: appointment.cancellation_reason,
-            cancelled_at: appointment.cancelled_at,
-            confirmation_code: appointment.confirmation_code,
-            confirmed_at: appointment.confirmed_at,
-            is_recurring: appointment.is_recurring,
-            recurring_pattern: appointment.recurring_pattern.map(|p| p.0),
-            parent_appointment_id: appointment.parent_appointment_id,
-            reminder_sent_email: appointment.reminder_sent_email,
-            reminder_sent_sms: appointment.reminder_sent_sms,
-            reminder_sent_whatsapp: appointment.reminder_sent_whatsapp,
-            checked_in_at: appointment.checked_in_at,
-            checked_out_at: appointment.checked_out_at,
-            created_at: appointment.created_at,
-            updated_at: appointment.updated_at,
-        }
+impl Appointment {
+    /// Decrypt the appointment into its API representation
+    pub fn decrypt(self, encryption_key: &EncryptionKey) -> anyhow::Result<AppointmentDto> {
+        let (reason, notes, cancellation_reason) = if self.text_encrypted {
+            (
+                encryption_key
+                    .decrypt_optional(&self.reason)
+                    .context("Failed to decrypt appointment reason")?,
+                encryption_key
+                    .decrypt_optional(&self.notes)
+                    .context("Failed to decrypt appointment notes")?,
+                encryption_key
+                    .decrypt_optional(&self.cancellation_reason)
+                    .context("Failed to decrypt cancellation reason")?,
+            )
+        } else {
+            (self.reason, self.notes, self.cancellation_reason)
+        };
+
+        Ok(AppointmentDto {
+            id: self.id,
+            patient_id: self.patient_id,
+            provider_id: self.provider_id,
+            scheduled_start: self.scheduled_start,
+            scheduled_end: self.scheduled_end,
+            duration_minutes: self.duration_minutes,
+            appointment_type: self.appointment_type,
+            reason,
+            notes,
+            status: self.status,
+            cancellation_reason,
+            cancelled_at: self.cancelled_at,
+            confirmation_code: self.confirmation_code,
+            confirmed_at: self.confirmed_at,
+            is_recurring: self.is_recurring,
+            recurring_pattern: self.recurring_pattern.map(|p| p.0),
+            parent_appointment_id: self.parent_appointment_id,
+            reminder_sent_email: self.reminder_sent_email,
+            reminder_sent_sms: self.reminder_sent_sms,
+            reminder_sent_whatsapp: self.reminder_sent_whatsapp,
+            checked_in_at: self.checked_in_at,
+            visit_started_at: self.visit_started_at,
+            checked_out_at: self.checked_out_at,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        })
     }
 }
 
@@ -341,6 +400,16 @@ pub struct CreateAppointmentRequest {
     /// Whether to send email confirmation notification to patient
     #[serde(default)]
     pub send_notification: Option<bool>,
+
+    /// Book over the provider's other appointments, as far as their
+    /// overbooking policy allows (needs the `overbook` permission)
+    #[serde(default)]
+    pub overbook: bool,
+
+    /// Rooms and equipment to reserve for the appointment
+    #[serde(default)]
+    #[validate(length(max = 5, message = "At most 5 resources can be reserved"))]
+    pub resource_ids: Vec<Uuid>,
 }
 
 impl CreateAppointmentRequest {
@@ -384,6 +453,20 @@ pub struct UpdateAppointmentRequest {
 
     /// Whether to send notification on status change (e.g., confirmation)
     pub send_notification: Option<bool>,
+
+    /// `updated_at` of the appointment as read; the update is refused with
+    /// 409 if it changed since (same as `If-Match`)
+    pub expected_version: Option<DateTime<Utc>>,
+
+    /// Move over the provider's other appointments, as far as their
+    /// overbooking policy allows (needs the `overbook` permission)
+    #[serde(default)]
+    pub overbook: bool,
+
+    /// Rooms and equipment reserved for the appointment, replacing those
+    /// reserved so far (`[]` releases them all)
+    #[validate(length(max = 5, message = "At most 5 resources can be reserved"))]
+    pub resource_ids: Option<Vec<Uuid>>,
 }
 
 /// Request to cancel an appointment
@@ -415,6 +498,9 @@ pub struct TimeSlot {
     pub start: DateTime<Utc>,
     pub end: DateTime<Utc>,
     pub available: bool,
+    /// Requested resources booked during the slot
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub busy_resources: Vec<Uuid>,
 }
 
 /// Availability response
@@ -423,6 +509,192 @@ pub struct AvailabilityResponse {
     pub date: DateTime<Utc>,
     pub provider_id: Uuid,
     pub slots: Vec<TimeSlot>,
+    /// Requested resource booked in the most slots, when one is
+    pub bottleneck_resource_id: Option<Uuid>,
+}
+
+/// Availability of one provider in a search across providers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderAvailability {
+    pub provider_id: Uuid,
+    pub provider_name: String,
+    pub slots: Vec<TimeSlot>,
+    /// Requested resource booked in the most of the provider's slots
+    pub bottleneck_resource_id: Option<Uuid>,
+}
+
+/// Free slot of one of the providers searched
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProviderSlot {
+    pub provider_id: Uuid,
+    pub provider_name: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+/// Availability response when searching several providers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MultiProviderAvailabilityResponse {
+    pub date: DateTime<Utc>,
+    /// Free slots of all the providers merged, earliest first
+    pub earliest_slots: Vec<ProviderSlot>,
+    pub providers: Vec<ProviderAvailability>,
+}
+
+/// Merge the free slots of several providers, earliest first
+///
+/// Slots starting at the same time keep the order of `providers`. At most
+/// `limit` slots are returned.
+pub fn earliest_slots(providers: &[ProviderAvailability], limit: usize) -> Vec<ProviderSlot> {
+    let mut slots: Vec<ProviderSlot> = providers
+        .iter()
+        .flat_map(|provider| {
+            provider
+                .slots
+                .iter()
+                .filter(|slot| slot.available)
+                .map(|slot| ProviderSlot {
+                    provider_id: provider.provider_id,
+                    provider_name: provider.provider_name.clone(),
+                    start: slot.start,
+                    end: slot.end,
+                })
+        })
+        .collect();
+
+    // Stable, so ties keep the provider order
+    slots.sort_by_key(|slot| slot.start);
+    slots.truncate(limit);
+    slots
+}
+
+/// Why an appointment cannot take place at the requested time
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SchedulingConflictKind {
+    /// Overlaps another appointment of the provider
+    Overlap,
+    Holiday,
+    NonWorkingDay,
+    OutsideWorkingHours,
+    /// Overlaps the break of the working day
+    BreakTime,
+    /// Overlaps the preparation or clean-up time around another appointment
+    BufferTime,
+    /// A requested resource is reserved by another appointment
+    ResourceBusy,
+}
+
+/// Reason an appointment time was refused
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchedulingConflict {
+    pub kind: SchedulingConflictKind,
+    pub message: String,
+    /// Blocked time: the overlapping appointment, the working hours or the
+    /// break (`None` for a whole day)
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+    /// Overlapping appointment (OVERLAP, BUFFER_TIME and RESOURCE_BUSY)
+    pub appointment_id: Option<Uuid>,
+    /// Reserved resource (RESOURCE_BUSY only)
+    pub resource_id: Option<Uuid>,
+}
+
+/// Free time suggested instead of a refused one
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AlternativeSlot {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+/// Preparation and clean-up minutes around an appointment of one type
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct AppointmentBuffer {
+    pub before: i64,
+    pub after: i64,
+}
+
+/// Buffer minutes per appointment type, from the `appointment.type_buffers`
+/// setting; types without an entry have none
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AppointmentBuffers(HashMap<AppointmentType, AppointmentBuffer>);
+
+impl AppointmentBuffers {
+    pub fn for_type(&self, appointment_type: AppointmentType) -> AppointmentBuffer {
+        self.0.get(&appointment_type).copied().unwrap_or_default()
+    }
+
+    /// Time an appointment keeps the provider busy, buffers included
+    pub fn blocked(
+        &self,
+        appointment_type: AppointmentType,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> (DateTime<Utc>, DateTime<Utc>) {
+        let buffer = self.for_type(appointment_type);
+        (
+            start - Duration::minutes(buffer.before),
+            end + Duration::minutes(buffer.after),
+        )
+    }
+
+    /// Time an appointment of another type cannot start in next to a booked
+    /// one: the booked appointment with its buffers, widened by the buffers
+    /// of the new appointment (its clean-up before, its preparation after)
+    pub fn busy(
+        &self,
+        booked_type: AppointmentType,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        new_type: Option<AppointmentType>,
+    ) -> (DateTime<Utc>, DateTime<Utc>) {
+        let (start, end) = self.blocked(booked_type, start, end);
+        let buffer = new_type.map(|t| self.for_type(t)).unwrap_or_default();
+        (
+            start - Duration::minutes(buffer.after),
+            end + Duration::minutes(buffer.before),
+        )
+    }
+
+    /// How far buffers can reach from an appointment, in minutes
+    pub fn reach(&self) -> i64 {
+        self.0
+            .values()
+            .map(|buffer| buffer.before.max(buffer.after))
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// Parse and validate the value of the `appointment.type_buffers` setting
+///
+/// An object keyed by appointment type, e.g.
+/// `{"ACUPUNCTURE": {"before": 10, "after": 5}}`.
+pub fn parse_appointment_buffers(
+    value: &serde_json::Value,
+) -> Result<AppointmentBuffers, String> {
+    let buffers: HashMap<AppointmentType, AppointmentBuffer> =
+        serde_json::from_value(value.clone()).map_err(|e| {
+            format!(
+                "Buffers must be an object of appointment types to before/after minutes: {}",
+                e
+            )
+        })?;
+
+    for (appointment_type, buffer) in &buffers {
+        for minutes in [buffer.before, buffer.after] {
+            if !(0..=MAX_BUFFER_MINUTES).contains(&minutes) {
+                return Err(format!(
+                    "Buffer minutes for {} must be between 0 and {}",
+                    serde_json::to_value(appointment_type).unwrap_or_default(),
+                    MAX_BUFFER_MINUTES
+                ));
+            }
+        }
+    }
+
+    Ok(AppointmentBuffers(buffers))
 }
 
 /// Search/filter parameters for appointments
@@ -450,12 +722,17 @@ pub struct AppointmentStatistics {
     pub upcoming_week: i64,
     pub no_show_rate: f64,
     pub cancellation_rate: f64,
+    /// Average minutes from arrival (`checked_in_at`) to the start of the
+    /// visit (`visit_started_at`)
+    pub avg_wait_minutes: f64,
+    /// Average minutes from the start of the visit to its completion
+    /// (`checked_out_at`)
+    pub avg_visit_duration_minutes: f64,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::Duration;
 
     // ==================== AppointmentStatus Tests ====================
 
@@ -477,6 +754,31 @@ mod tests {
         assert!(AppointmentStatus::Completed.is_final());
     }
 
+    #[test]
+    fn test_pending_transitions() {
+        assert!(AppointmentStatus::Pending.can_transition_to(&AppointmentStatus::Scheduled));
+        assert!(AppointmentStatus::Pending.can_transition_to(&AppointmentStatus::Confirmed));
+        assert!(AppointmentStatus::Pending.can_transition_to(&AppointmentStatus::Cancelled));
+        assert!(!AppointmentStatus::Pending.can_transition_to(&AppointmentStatus::InProgress));
+        assert!(!AppointmentStatus::Pending.can_transition_to(&AppointmentStatus::NoShow));
+        assert!(!AppointmentStatus::Scheduled.can_transition_to(&AppointmentStatus::Pending));
+        assert!(!AppointmentStatus::Pending.is_final());
+    }
+
+    #[test]
+    fn test_arrived_transitions() {
+        assert!(AppointmentStatus::Scheduled.can_transition_to(&AppointmentStatus::Arrived));
+        assert!(AppointmentStatus::Confirmed.can_transition_to(&AppointmentStatus::Arrived));
+        assert!(!AppointmentStatus::Pending.can_transition_to(&AppointmentStatus::Arrived));
+        assert!(AppointmentStatus::Arrived.can_transition_to(&AppointmentStatus::InProgress));
+        assert!(AppointmentStatus::Arrived.can_transition_to(&AppointmentStatus::Cancelled));
+        assert!(AppointmentStatus::Arrived.can_transition_to(&AppointmentStatus::NoShow));
+        assert!(!AppointmentStatus::Arrived.can_transition_to(&AppointmentStatus::Completed));
+        assert!(!AppointmentStatus::Arrived.can_transition_to(&AppointmentStatus::Confirmed));
+        assert!(!AppointmentStatus::InProgress.can_transition_to(&AppointmentStatus::Arrived));
+        assert!(!AppointmentStatus::Arrived.is_final());
+    }
+
     #[test]
     fn test_scheduled_can_transition_to_self() {
         assert!(AppointmentStatus::Scheduled.can_transition_to(&AppointmentStatus::Scheduled));
@@ -566,6 +868,7 @@ mod tests {
         assert_eq!(AppointmentType::NewPatient.default_duration(), 60);
         assert_eq!(AppointmentType::FollowUp.default_duration(), 30);
         assert_eq!(AppointmentType::Acupuncture.default_duration(), 45);
+        assert_eq!(AppointmentType::Televisit.default_duration(), 30);
     }
 
     #[test]
@@ -670,6 +973,7 @@ mod tests {
             start: Utc::now(),
             end: Utc::now() + Duration::minutes(30),
             available: true,
+            busy_resources: Vec::new(),
         };
         assert!(slot.available);
     }
@@ -680,10 +984,50 @@ mod tests {
             start: Utc::now(),
             end: Utc::now() + Duration::minutes(30),
             available: false,
+            busy_resources: Vec::new(),
         };
         assert!(!slot.available);
     }
 
+    #[test]
+    fn test_earliest_slots_merges_providers() {
+        let start = Utc::now();
+        let slot = |minutes: i64, available: bool| TimeSlot {
+            start: start + Duration::minutes(minutes),
+            end: start + Duration::minutes(minutes + 30),
+            available,
+            busy_resources: Vec::new(),
+        };
+        let provider = |name: &str, slots: Vec<TimeSlot>| ProviderAvailability {
+            provider_id: Uuid::new_v4(),
+            provider_name: name.to_string(),
+            slots,
+            bottleneck_resource_id: None,
+        };
+        let providers = vec![
+            provider("Mario Rossi", vec![slot(0, false), slot(30, true), slot(60, true)]),
+            provider("Anna Bianchi", vec![slot(0, true), slot(30, true), slot(60, false)]),
+        ];
+
+        let merged = earliest_slots(&providers, 10);
+        let order: Vec<(&str, i64)> = merged
+            .iter()
+            .map(|s| (s.provider_name.as_str(), (s.start - start).num_minutes()))
+            .collect();
+        assert_eq!(
+            order,
+            vec![
+                ("Anna Bianchi", 0),
+                ("Mario Rossi", 30),
+                ("Anna Bianchi", 30),
+                ("Mario Rossi", 60),
+            ]
+        );
+
+        assert_eq!(earliest_slots(&providers, 2).len(), 2);
+        assert!(earliest_slots(&[], 10).is_empty());
+    }
+
     // ==================== AppointmentSearchFilter Tests ====================
 
     #[test]
@@ -799,6 +1143,8 @@ mod tests {
             upcoming_week: 10,
             no_show_rate: 5.5,
             cancellation_rate: 8.2,
+            avg_wait_minutes: 12.5,
+            avg_visit_duration_minutes: 24.0,
         };
 
         assert_eq!(stats.total, 35);
@@ -806,6 +1152,7 @@ mod tests {
         assert_eq!(stats.upcoming_week, 10);
         assert!((stats.no_show_rate - 5.5).abs() < f64::EPSILON);
         assert!((stats.cancellation_rate - 8.2).abs() < f64::EPSILON);
+        assert!((stats.avg_wait_minutes - 12.5).abs() < f64::EPSILON);
     }
 
     #[test]
@@ -818,6 +1165,8 @@ mod tests {
             upcoming_week: 0,
             no_show_rate: 0.0,
             cancellation_rate: 0.0,
+            avg_wait_minutes: 0.0,
+            avg_visit_duration_minutes: 0.0,
         };
 
         assert_eq!(stats.total, 0);
@@ -873,6 +1222,8 @@ mod tests {
             is_recurring: None,
             recurring_pattern: None,
             send_notification: Some(true),
+            overbook: false,
+            resource_ids: Vec::new(),
         };
         assert!(request.validate_appointment().is_ok());
     }
@@ -890,6 +1241,8 @@ mod tests {
             is_recurring: None,
             recurring_pattern: None,
             send_notification: None,
+            overbook: false,
+            resource_ids: Vec::new(),
         };
         assert!(request.validate_appointment().is_err());
     }
@@ -907,6 +1260,8 @@ mod tests {
             is_recurring: Some(true), // Recurring but no pattern
             recurring_pattern: None,
             send_notification: None,
+            overbook: false,
+            resource_ids: Vec::new(),
         };
         let result = request.validate_appointment();
         assert!(result.is_err());
@@ -932,6 +1287,8 @@ mod tests {
             is_recurring: Some(true),
             recurring_pattern: Some(pattern),
             send_notification: Some(true),
+            overbook: false,
+            resource_ids: Vec::new(),
         };
         assert!(request.validate_appointment().is_ok());
     }
@@ -989,6 +1346,10 @@ mod tests {
         let apt_type = AppointmentType::RoutineCheckup;
         let json = serde_json::to_string(&apt_type).unwrap();
         assert_eq!(json, "\"ROUTINE_CHECKUP\"");
+
+        let apt_type = AppointmentType::Televisit;
+        let json = serde_json::to_string(&apt_type).unwrap();
+        assert_eq!(json, "\"TELEVISIT\"");
     }
 
     #[test]
@@ -998,15 +1359,149 @@ mod tests {
         assert_eq!(json, "\"BI_WEEKLY\"");
     }
 
+    // ==================== AppointmentBuffers Tests ====================
+
+    #[test]
+    fn test_parse_appointment_buffers() {
+        let buffers = parse_appointment_buffers(&serde_json::json!({
+            "ACUPUNCTURE": {"before": 10, "after": 5},
+            "NEW_PATIENT": {"after": 15},
+        }))
+        .unwrap();
+
+        assert_eq!(
+            buffers.for_type(AppointmentType::Acupuncture),
+            AppointmentBuffer { before: 10, after: 5 }
+        );
+        assert_eq!(
+            buffers.for_type(AppointmentType::NewPatient),
+            AppointmentBuffer { before: 0, after: 15 }
+        );
+        assert_eq!(buffers.for_type(AppointmentType::FollowUp), AppointmentBuffer::default());
+        assert_eq!(buffers.reach(), 15);
+
+        assert!(parse_appointment_buffers(&serde_json::json!({})).unwrap().0.is_empty());
+    }
+
+    #[test]
+    fn test_parse_appointment_buffers_rejects_invalid() {
+        assert!(parse_appointment_buffers(&serde_json::json!([])).is_err());
+        assert!(parse_appointment_buffers(&serde_json::json!({"MASSAGE": {"before": 5}})).is_err());
+        assert!(parse_appointment_buffers(&serde_json::json!({"URGENT": {"during": 5}})).is_err());
+        assert!(parse_appointment_buffers(&serde_json::json!({"URGENT": {"before": -5}})).is_err());
+        assert!(parse_appointment_buffers(&serde_json::json!({"URGENT": {"after": 121}})).is_err());
+    }
+
+    #[test]
+    fn test_appointment_buffers_busy_interval() {
+        let buffers = parse_appointment_buffers(&serde_json::json!({
+            "ACUPUNCTURE": {"before": 10, "after": 5},
+            "CONSULTATION": {"before": 0, "after": 15},
+        }))
+        .unwrap();
+        let start = Utc::now();
+        let end = start + Duration::minutes(30);
+
+        // A 30-minute acupuncture session blocks 45 minutes
+        assert_eq!(
+            buffers.blocked(AppointmentType::Acupuncture, start, end),
+            (start - Duration::minutes(10), end + Duration::minutes(5))
+        );
+        // A consultation needs its clean-up to end before the session's preparation
+        assert_eq!(
+            buffers.busy(
+                AppointmentType::Acupuncture,
+                start,
+                end,
+                Some(AppointmentType::Consultation)
+            ),
+            (start - Duration::minutes(25), end + Duration::minutes(5))
+        );
+        assert_eq!(
+            buffers.busy(AppointmentType::FollowUp, start, end, None),
+            (start, end)
+        );
+    }
+
     #[test]
     fn test_time_slot_json_roundtrip() {
         let slot = TimeSlot {
             start: Utc::now(),
             end: Utc::now() + Duration::minutes(30),
             available: true,
+            busy_resources: Vec::new(),
         };
         let json = serde_json::to_string(&slot).unwrap();
         let deserialized: TimeSlot = serde_json::from_str(&json).unwrap();
         assert_eq!(slot.available, deserialized.available);
     }
+
+    // ==================== Decryption Tests ====================
+
+    fn appointment_with_text(
+        reason: Option<String>,
+        notes: Option<String>,
+        text_encrypted: bool,
+    ) -> Appointment {
+        let start = Utc::now() + Duration::days(1);
+        Appointment {
+            id: Uuid::new_v4(),
+            patient_id: Uuid::new_v4(),
+            provider_id: Uuid::new_v4(),
+            scheduled_start: start,
+            scheduled_end: start + Duration::minutes(30),
+            duration_minutes: 30,
+            appointment_type: AppointmentType::FollowUp,
+            reason,
+            notes,
+            status: AppointmentStatus::Scheduled,
+            cancellation_reason: None,
+            cancelled_at: None,
+            cancelled_by: None,
+            confirmation_code: None,
+            confirmed_at: None,
+            is_recurring: false,
+            recurring_pattern: None,
+            parent_appointment_id: None,
+            reminder_sent_email: false,
+            reminder_sent_sms: false,
+            reminder_sent_whatsapp: false,
+            reminder_sent_at: None,
+            checked_in_at: None,
+            visit_started_at: None,
+            checked_out_at: None,
+            text_encrypted,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            created_by: None,
+            updated_by: None,
+        }
+    }
+
+    #[test]
+    fn test_decrypt_encrypted_appointment_text() {
+        let key =
+            EncryptionKey::from_base64("MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWY=").unwrap();
+        let reason = key.encrypt("Chest pain on exertion").unwrap();
+        let appointment = appointment_with_text(Some(reason), None, true);
+
+        let dto = appointment.decrypt(&key).unwrap();
+        assert_eq!(dto.reason.as_deref(), Some("Chest pain on exertion"));
+        assert_eq!(dto.notes, None);
+    }
+
+    #[test]
+    fn test_decrypt_legacy_plaintext_appointment_text() {
+        let key =
+            EncryptionKey::from_base64("MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWY=").unwrap();
+        let appointment = appointment_with_text(
+            Some("Annual checkup".to_string()),
+            Some("Bring previous lab results".to_string()),
+            false,
+        );
+
+        let dto = appointment.decrypt(&key).unwrap();
+        assert_eq!(dto.reason.as_deref(), Some("Annual checkup"));
+        assert_eq!(dto.notes.as_deref(), Some("Bring previous lab results"));
+    }
 }
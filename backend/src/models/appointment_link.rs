@@ -0,0 +1,104 @@
+/*!
+ * Appointment Link Model
+ *
+ * Appointment reminders carry two links, one to confirm the appointment and
+ * one to cancel it. Each link holds a signed token naming the appointment,
+ * the patient and the action; it expires when the appointment starts. The
+ * tokens are made and checked by `NotificationService`.
+ */
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::models::appointment::{AppointmentStatus, AppointmentType};
+
+/// What an appointment link does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AppointmentLinkAction {
+    Confirm,
+    Cancel,
+}
+
+impl AppointmentLinkAction {
+    /// Form used in tokens
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Confirm => "confirm",
+            Self::Cancel => "cancel",
+        }
+    }
+
+    /// Parse the form used in tokens
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "confirm" => Some(Self::Confirm),
+            "cancel" => Some(Self::Cancel),
+            _ => None,
+        }
+    }
+}
+
+/// Contents of a valid appointment link token
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AppointmentLinkClaims {
+    pub appointment_id: Uuid,
+    pub patient_id: Uuid,
+    pub action: AppointmentLinkAction,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Appointment after a link was used, as seen by the patient
+#[derive(Debug, Clone)]
+pub struct AppointmentLinkOutcome {
+    pub appointment_id: Uuid,
+    pub status: AppointmentStatus,
+    pub scheduled_start: DateTime<Utc>,
+    pub scheduled_end: DateTime<Utc>,
+    pub appointment_type: AppointmentType,
+    pub provider_name: String,
+    /// Whether the link changed the appointment (`false` when it was
+    /// already confirmed or cancelled)
+    pub changed: bool,
+}
+
+/// Request body for POST /api/v1/appointment-links/respond
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct AppointmentLinkRequest {
+    #[validate(length(min = 1, max = 256, message = "Invalid link"))]
+    pub token: String,
+}
+
+/// Appointment after a link was used
+#[derive(Debug, Clone, Serialize)]
+pub struct AppointmentLinkResponse {
+    pub action: AppointmentLinkAction,
+    pub status: AppointmentStatus,
+    pub scheduled_start: DateTime<Utc>,
+    pub scheduled_end: DateTime<Utc>,
+    #[serde(rename = "type")]
+    pub appointment_type: AppointmentType,
+    pub provider_name: String,
+    pub message: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_action_round_trip() {
+        for action in [
+            AppointmentLinkAction::Confirm,
+            AppointmentLinkAction::Cancel,
+        ] {
+            assert_eq!(
+                AppointmentLinkAction::from_str(action.as_str()),
+                Some(action)
+            );
+        }
+        assert_eq!(AppointmentLinkAction::from_str("CONFIRM"), None);
+    }
+}
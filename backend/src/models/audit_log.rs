@@ -16,6 +16,8 @@ use serde::{Deserialize, Serialize};
 use sqlx::{types::ipnetwork::IpNetwork, PgPool};
 use uuid::Uuid;
 
+use crate::services::siem_exporter::{self, SecurityEvent, SecurityEventKind};
+
 /// Audit action types
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
@@ -85,6 +87,34 @@ pub enum EntityType {
     File,
     Template,
     Notification,
+    UserInvitation,
+    Report,
+    AccessDelegation,
+    AuthorizationPolicy,
+    RetentionRule,
+    Consent,
+    PatientAttachment,
+    PatientAllergy,
+    PatientProblem,
+    LabOrder,
+    LabResult,
+    ImagingOrder,
+    Referral,
+    VisitCalculation,
+    MedicationSync,
+    VisitAddendum,
+    VisitAttachment,
+    RateLimitQuota,
+    ReportSubscription,
+    PatientCohort,
+    RecallRule,
+    PatientCommunication,
+    PatientMessageThread,
+    PortalSession,
+    VisitSurvey,
+    TelevisitSession,
+    WaitlistEntry,
+    Resource,
 }
 
 impl EntityType {
@@ -93,7 +123,13 @@ impl EntityType {
         vec![
             "PATIENT", "PATIENT_INSURANCE", "VISIT", "PRESCRIPTION", "DIAGNOSIS",
             "APPOINTMENT", "USER", "DOCUMENT", "HOLIDAY", "WORKING_HOURS", "SYSTEM_SETTING",
-            "FILE", "TEMPLATE", "NOTIFICATION"
+            "FILE", "TEMPLATE", "NOTIFICATION", "USER_INVITATION", "REPORT", "ACCESS_DELEGATION",
+            "AUTHORIZATION_POLICY", "RETENTION_RULE", "CONSENT", "PATIENT_ATTACHMENT",
+            "PATIENT_ALLERGY", "PATIENT_PROBLEM", "LAB_ORDER", "LAB_RESULT", "IMAGING_ORDER",
+            "REFERRAL", "VISIT_CALCULATION", "MEDICATION_SYNC", "VISIT_ADDENDUM",
+            "VISIT_ATTACHMENT", "RATE_LIMIT_QUOTA", "REPORT_SUBSCRIPTION", "PATIENT_COHORT",
+            "RECALL_RULE", "PATIENT_COMMUNICATION", "PATIENT_MESSAGE_THREAD",
+            "PORTAL_SESSION", "VISIT_SURVEY", "TELEVISIT_SESSION", "WAITLIST_ENTRY", "RESOURCE"
         ]
     }
 
@@ -114,6 +150,34 @@ impl EntityType {
             "FILE" => Some(Self::File),
             "TEMPLATE" => Some(Self::Template),
             "NOTIFICATION" => Some(Self::Notification),
+            "USER_INVITATION" => Some(Self::UserInvitation),
+            "REPORT" => Some(Self::Report),
+            "ACCESS_DELEGATION" => Some(Self::AccessDelegation),
+            "AUTHORIZATION_POLICY" => Some(Self::AuthorizationPolicy),
+            "RETENTION_RULE" => Some(Self::RetentionRule),
+            "CONSENT" => Some(Self::Consent),
+            "PATIENT_ATTACHMENT" => Some(Self::PatientAttachment),
+            "PATIENT_ALLERGY" => Some(Self::PatientAllergy),
+            "PATIENT_PROBLEM" => Some(Self::PatientProblem),
+            "LAB_ORDER" => Some(Self::LabOrder),
+            "LAB_RESULT" => Some(Self::LabResult),
+            "IMAGING_ORDER" => Some(Self::ImagingOrder),
+            "REFERRAL" => Some(Self::Referral),
+            "VISIT_CALCULATION" => Some(Self::VisitCalculation),
+            "MEDICATION_SYNC" => Some(Self::MedicationSync),
+            "VISIT_ADDENDUM" => Some(Self::VisitAddendum),
+            "VISIT_ATTACHMENT" => Some(Self::VisitAttachment),
+            "RATE_LIMIT_QUOTA" => Some(Self::RateLimitQuota),
+            "REPORT_SUBSCRIPTION" => Some(Self::ReportSubscription),
+            "PATIENT_COHORT" => Some(Self::PatientCohort),
+            "RECALL_RULE" => Some(Self::RecallRule),
+            "PATIENT_COMMUNICATION" => Some(Self::PatientCommunication),
+            "PATIENT_MESSAGE_THREAD" => Some(Self::PatientMessageThread),
+            "PORTAL_SESSION" => Some(Self::PortalSession),
+            "VISIT_SURVEY" => Some(Self::VisitSurvey),
+            "TELEVISIT_SESSION" => Some(Self::TelevisitSession),
+            "WAITLIST_ENTRY" => Some(Self::WaitlistEntry),
+            "RESOURCE" => Some(Self::Resource),
             _ => None,
         }
     }
@@ -136,6 +200,34 @@ impl std::fmt::Display for EntityType {
             Self::File => write!(f, "FILE"),
             Self::Template => write!(f, "TEMPLATE"),
             Self::Notification => write!(f, "NOTIFICATION"),
+            Self::UserInvitation => write!(f, "USER_INVITATION"),
+            Self::Report => write!(f, "REPORT"),
+            Self::AccessDelegation => write!(f, "ACCESS_DELEGATION"),
+            Self::AuthorizationPolicy => write!(f, "AUTHORIZATION_POLICY"),
+            Self::RetentionRule => write!(f, "RETENTION_RULE"),
+            Self::Consent => write!(f, "CONSENT"),
+            Self::PatientAttachment => write!(f, "PATIENT_ATTACHMENT"),
+            Self::PatientAllergy => write!(f, "PATIENT_ALLERGY"),
+            Self::PatientProblem => write!(f, "PATIENT_PROBLEM"),
+            Self::LabOrder => write!(f, "LAB_ORDER"),
+            Self::LabResult => write!(f, "LAB_RESULT"),
+            Self::ImagingOrder => write!(f, "IMAGING_ORDER"),
+            Self::Referral => write!(f, "REFERRAL"),
+            Self::VisitCalculation => write!(f, "VISIT_CALCULATION"),
+            Self::MedicationSync => write!(f, "MEDICATION_SYNC"),
+            Self::VisitAddendum => write!(f, "VISIT_ADDENDUM"),
+            Self::VisitAttachment => write!(f, "VISIT_ATTACHMENT"),
+            Self::RateLimitQuota => write!(f, "RATE_LIMIT_QUOTA"),
+            Self::ReportSubscription => write!(f, "REPORT_SUBSCRIPTION"),
+            Self::PatientCohort => write!(f, "PATIENT_COHORT"),
+            Self::RecallRule => write!(f, "RECALL_RULE"),
+            Self::PatientCommunication => write!(f, "PATIENT_COMMUNICATION"),
+            Self::PatientMessageThread => write!(f, "PATIENT_MESSAGE_THREAD"),
+            Self::PortalSession => write!(f, "PORTAL_SESSION"),
+            Self::VisitSurvey => write!(f, "VISIT_SURVEY"),
+            Self::TelevisitSession => write!(f, "TELEVISIT_SESSION"),
+            Self::WaitlistEntry => write!(f, "WAITLIST_ENTRY"),
+            Self::Resource => write!(f, "RESOURCE"),
         }
     }
 }
@@ -371,6 +463,235 @@ pub struct UserActivitySummary {
     pub recent_logs: Vec<AuditLogResponse>,
 }
 
+// ============================================================================
+// Access Review DTOs
+// ============================================================================
+
+/// Query parameters for the per-user activity report
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct UserActivityReportFilter {
+    /// Period start (inclusive, default: 30 days before end)
+    pub start_date: Option<NaiveDate>,
+    /// Period end (inclusive, default: today)
+    pub end_date: Option<NaiveDate>,
+    /// Only include users with this role (ADMIN, DOCTOR)
+    pub role: Option<String>,
+}
+
+/// Maximum length of an activity report period in days
+pub const MAX_ACTIVITY_REPORT_DAYS: i64 = 366;
+
+impl UserActivityReportFilter {
+    /// Resolve the report period against today's date
+    ///
+    /// # Errors
+    ///
+    /// Returns a message if the period is inverted or longer than a year
+    pub fn period(&self, today: NaiveDate) -> Result<(NaiveDate, NaiveDate), String> {
+        let end = self.end_date.unwrap_or(today).min(today);
+        let start = self
+            .start_date
+            .unwrap_or_else(|| end - chrono::Duration::days(29));
+
+        if start > end {
+            return Err("start_date must be on or before end_date".to_string());
+        }
+        if (end - start).num_days() >= MAX_ACTIVITY_REPORT_DAYS {
+            return Err(format!(
+                "Report period cannot exceed {} days",
+                MAX_ACTIVITY_REPORT_DAYS
+            ));
+        }
+
+        Ok((start, end))
+    }
+}
+
+/// Activity totals for one user over the report period
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserActivityReportRow {
+    pub user_id: Uuid,
+    pub username: Option<String>,
+    pub full_name: Option<String>,
+    pub role: Option<String>,
+    /// Successful logins
+    pub logins: i64,
+    /// Distinct patients whose records were accessed
+    pub patients_accessed: i64,
+    /// Requests touching patient records
+    pub patient_access_events: i64,
+    /// Documents generated (single documents and print bundles)
+    pub documents_generated: i64,
+    /// Requests outside clinic working hours (Europe/Rome)
+    pub after_hours_events: i64,
+    /// All audited requests
+    pub total_events: i64,
+    /// Days with any activity
+    pub active_days: i64,
+}
+
+/// Activity totals for one role over the report period
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoleActivitySummary {
+    pub role: String,
+    pub users: i64,
+    pub logins: i64,
+    pub documents_generated: i64,
+    pub after_hours_events: i64,
+    pub total_events: i64,
+}
+
+/// Per-user activity report for periodic access reviews
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserActivityReport {
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub generated_at: DateTime<Utc>,
+    pub users: Vec<UserActivityReportRow>,
+    pub roles: Vec<RoleActivitySummary>,
+}
+
+// ============================================================================
+// Patient Access Log DTOs
+// ============================================================================
+
+/// Filter parameters for a patient's record access log
+#[derive(Debug, Clone, Deserialize)]
+pub struct PatientAccessLogFilter {
+    /// Accesses from this date (inclusive)
+    pub date_from: Option<NaiveDate>,
+    /// Accesses until this date (inclusive)
+    pub date_to: Option<NaiveDate>,
+    /// Only accesses by this user
+    pub user_id: Option<Uuid>,
+    /// Page number (1-indexed)
+    #[serde(default = "default_page")]
+    pub page: i64,
+    /// Number of items per page (max 100)
+    #[serde(default = "default_page_size")]
+    pub page_size: i64,
+}
+
+impl PatientAccessLogFilter {
+    /// Validate and sanitize filter parameters
+    pub fn validate(&mut self) {
+        if self.page < 1 {
+            self.page = 1;
+        }
+        if self.page_size < 1 {
+            self.page_size = 50;
+        }
+        if self.page_size > 100 {
+            self.page_size = 100;
+        }
+    }
+
+    /// Calculate offset for pagination
+    pub fn offset(&self) -> i64 {
+        (self.page - 1) * self.page_size
+    }
+}
+
+/// Single access to a patient's chart (one request)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatientAccessEntry {
+    pub accessed_at: DateTime<Utc>,
+    pub user_id: Uuid,
+    pub username: Option<String>,
+    pub full_name: Option<String>,
+    pub role: Option<String>,
+    /// Admin acting as the user during an impersonation session
+    pub impersonator_id: Option<Uuid>,
+    /// VIEW, MODIFY or EXPORT
+    pub access_type: String,
+    /// Part of the chart accessed (PATIENT, VISIT, PRESCRIPTION, ...)
+    pub record_type: String,
+    pub record_id: Option<String>,
+    /// Audit action as logged (e.g. UPDATE, or GET /api/v1/patients/{id})
+    pub action: String,
+    pub ip_address: Option<String>,
+    pub request_id: Option<Uuid>,
+}
+
+/// Accesses to a patient's chart by one user
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatientAccessorSummary {
+    pub user_id: Uuid,
+    pub username: Option<String>,
+    pub full_name: Option<String>,
+    pub role: Option<String>,
+    pub views: i64,
+    pub modifications: i64,
+    pub exports: i64,
+    pub first_access: DateTime<Utc>,
+    pub last_access: DateTime<Utc>,
+}
+
+/// Who viewed or modified a patient's chart, and when
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatientAccessLog {
+    pub patient_id: Uuid,
+    /// Per-user totals over the filtered period (not paginated)
+    pub accessors: Vec<PatientAccessorSummary>,
+    /// Individual accesses, newest first
+    pub entries: Vec<PatientAccessEntry>,
+    pub total: i64,
+    pub page: i64,
+    pub page_size: i64,
+    pub total_pages: i64,
+}
+
+// ============================================================================
+// Hash Chain DTOs
+// ============================================================================
+
+/// Kind of integrity problem found in the audit log hash chain
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AuditChainIssueKind {
+    /// Sequence numbers are missing (rows deleted)
+    Gap,
+    /// The same sequence number appears more than once
+    DuplicateSequence,
+    /// prev_hash does not match the row_hash of the previous row
+    BrokenLink,
+    /// row_hash does not match the recomputed hash (row modified)
+    HashMismatch,
+    /// The chain does not end where the chain head says it does
+    HeadMismatch,
+}
+
+/// Single integrity problem in the audit log hash chain
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditChainIssue {
+    pub kind: AuditChainIssueKind,
+    /// Sequence number the problem was detected at
+    pub chain_seq: i64,
+    /// Audit log row involved, when there is one
+    pub audit_log_id: Option<i64>,
+    pub detail: String,
+}
+
+/// Result of recomputing the audit log hash chain
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditChainReport {
+    /// True when no issues were found
+    pub valid: bool,
+    /// Chained rows checked
+    pub rows_checked: i64,
+    /// Rows written before chaining was enabled
+    pub unchained_rows: i64,
+    /// Last sequence number recorded in the chain head
+    pub head_seq: i64,
+    /// Last sequence number removed by audit log retention (0 if none)
+    pub pruned_seq: i64,
+    /// Total issues found
+    pub issue_count: i64,
+    /// First issues found (capped)
+    pub issues: Vec<AuditChainIssue>,
+    pub verified_at: DateTime<Utc>,
+}
+
 // ============================================================================
 // Export DTOs
 // ============================================================================
@@ -439,13 +760,33 @@ impl AuditLog {
         // Convert changes to JSONB
         let changes_jsonb = log.changes.map(|c| sqlx::types::JsonValue::from(c));
 
+        // Admin acting as user_id, when written during an impersonation session
+        let impersonator_id = crate::models::impersonation::current_impersonator();
+
+        // Handler-written row replaces the automatic domain event for this request
+        crate::models::domain_event::mark_explicit_audit();
+
+        // Streamed without `changes`, which may contain clinical data
+        let security_event = siem_exporter::is_enabled().then(|| SecurityEvent {
+            user_id: log.user_id,
+            ip_address: log.ip_address.clone(),
+            request_id: log.request_id,
+            entity_type: Some(log.entity_type.to_string()),
+            entity_id: log.entity_id.clone(),
+            ..SecurityEvent::new(
+                SecurityEventKind::Audit,
+                log.action.to_string(),
+                format!("{} {}", log.action, log.entity_type),
+            )
+        });
+
         sqlx::query(
             r#"
             INSERT INTO audit_logs (
                 user_id, action, entity_type, entity_id,
-                changes, ip_address, user_agent, request_id
+                changes, ip_address, user_agent, request_id, impersonator_id
             )
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
             "#,
         )
         .bind(log.user_id)
@@ -456,9 +797,14 @@ impl AuditLog {
         .bind(ip_network)
         .bind(log.user_agent)
         .bind(log.request_id)
+        .bind(impersonator_id)
         .execute(pool)
         .await?;
 
+        if let Some(event) = security_event {
+            siem_exporter::emit(event);
+        }
+
         Ok(())
     }
 
@@ -589,6 +935,37 @@ mod tests {
         assert_eq!(deserialized, AuditAction::Create);
     }
 
+    #[test]
+    fn test_user_activity_report_period() {
+        let today = NaiveDate::from_ymd_opt(2026, 3, 15).unwrap();
+
+        let (start, end) = UserActivityReportFilter::default().period(today).unwrap();
+        assert_eq!(end, today);
+        assert_eq!(start, NaiveDate::from_ymd_opt(2026, 2, 14).unwrap());
+
+        // End dates in the future are clamped to today
+        let filter = UserActivityReportFilter {
+            start_date: NaiveDate::from_ymd_opt(2026, 3, 1),
+            end_date: NaiveDate::from_ymd_opt(2026, 4, 1),
+            role: None,
+        };
+        assert_eq!(filter.period(today).unwrap().1, today);
+
+        let inverted = UserActivityReportFilter {
+            start_date: NaiveDate::from_ymd_opt(2026, 3, 10),
+            end_date: NaiveDate::from_ymd_opt(2026, 3, 1),
+            role: None,
+        };
+        assert!(inverted.period(today).is_err());
+
+        let too_long = UserActivityReportFilter {
+            start_date: NaiveDate::from_ymd_opt(2024, 1, 1),
+            end_date: None,
+            role: None,
+        };
+        assert!(too_long.period(today).is_err());
+    }
+
     #[test]
     fn test_audit_logs_filter_validation() {
         let mut filter = AuditLogsFilter {
@@ -611,6 +988,27 @@ mod tests {
         assert_eq!(filter.offset(), 50);
     }
 
+    #[test]
+    fn test_patient_access_log_filter_validation() {
+        let mut filter = PatientAccessLogFilter {
+            date_from: None,
+            date_to: None,
+            user_id: None,
+            page: 0,
+            page_size: 0,
+        };
+        filter.validate();
+        assert_eq!(filter.page, 1);
+        assert_eq!(filter.page_size, 50);
+        assert_eq!(filter.offset(), 0);
+
+        filter.page = 2;
+        filter.page_size = 500;
+        filter.validate();
+        assert_eq!(filter.page_size, 100);
+        assert_eq!(filter.offset(), 100);
+    }
+
     #[test]
     fn test_export_format_default() {
         let format = ExportFormat::default();
@@ -0,0 +1,192 @@
+/*!
+ * Authorization Policy Model
+ *
+ * Request and response types for runtime Casbin policy management and for
+ * permission introspection by the current user. Rules are
+ * `p, role, resource, action` (permission) and `g, member, role` (role
+ * assignment: `member` inherits every permission of `role`).
+ */
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Maximum length of a role, resource, or action name (column width)
+pub const MAX_POLICY_TOKEN_LEN: usize = 128;
+
+/// Validate a role, resource, or action name
+///
+/// Names are single tokens: letters, digits, `_`, `-`, `.` and `:`. Commas and
+/// whitespace would break the policy file format used for seeding.
+fn validate_token(field: &str, value: &str) -> Result<(), String> {
+    if value.is_empty() {
+        return Err(format!("{} is required", field));
+    }
+    if value.len() > MAX_POLICY_TOKEN_LEN {
+        return Err(format!(
+            "{} cannot exceed {} characters",
+            field, MAX_POLICY_TOKEN_LEN
+        ));
+    }
+    if !value
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | ':'))
+    {
+        return Err(format!(
+            "{} may only contain letters, digits, '_', '-', '.' and ':'",
+            field
+        ));
+    }
+    Ok(())
+}
+
+/// Permission rule: `role` may perform `action` on `resource`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PolicyRule {
+    pub role: String,
+    pub resource: String,
+    pub action: String,
+}
+
+impl PolicyRule {
+    /// Build from a Casbin `p` rule (`[role, resource, action]`)
+    pub fn from_rule(rule: &[String]) -> Option<Self> {
+        match rule {
+            [role, resource, action, ..] => Some(Self {
+                role: role.clone(),
+                resource: resource.clone(),
+                action: action.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        validate_token("role", &self.role)?;
+        validate_token("resource", &self.resource)?;
+        validate_token("action", &self.action)
+    }
+}
+
+/// Role assignment: `member` inherits the permissions of `role`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoleAssignment {
+    pub member: String,
+    pub role: String,
+}
+
+impl RoleAssignment {
+    /// Build from a Casbin `g` rule (`[member, role]`)
+    pub fn from_rule(rule: &[String]) -> Option<Self> {
+        match rule {
+            [member, role, ..] => Some(Self {
+                member: member.clone(),
+                role: role.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        validate_token("member", &self.member)?;
+        validate_token("role", &self.role)?;
+        if self.member == self.role {
+            return Err("A role cannot be assigned to itself".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Current policy set
+#[derive(Debug, Serialize)]
+pub struct PolicyListResponse {
+    pub policies: Vec<PolicyRule>,
+    pub role_assignments: Vec<RoleAssignment>,
+}
+
+/// Effective permission matrix of the current user
+#[derive(Debug, Serialize)]
+pub struct EffectivePermissionsResponse {
+    pub role: String,
+    /// Resource -> allowed actions (sorted)
+    pub permissions: BTreeMap<String, Vec<String>>,
+}
+
+/// Request body for checking a single permission
+#[derive(Debug, Clone, Deserialize)]
+pub struct PermissionCheckRequest {
+    pub resource: String,
+    pub action: String,
+}
+
+impl PermissionCheckRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        validate_token("resource", &self.resource)?;
+        validate_token("action", &self.action)
+    }
+}
+
+/// Result of a permission check
+#[derive(Debug, Serialize)]
+pub struct PermissionCheckResponse {
+    pub resource: String,
+    pub action: String,
+    pub allowed: bool,
+}
+
+/// Result of a policy reload
+#[derive(Debug, Serialize)]
+pub struct PolicyReloadResponse {
+    pub policies: usize,
+    pub role_assignments: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(role: &str, resource: &str, action: &str) -> PolicyRule {
+        PolicyRule {
+            role: role.to_string(),
+            resource: resource.to_string(),
+            action: action.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_policy_rule_validation() {
+        assert!(rule("DOCTOR", "audit_logs", "read").validate().is_ok());
+        assert!(rule("DOCTOR", "delegations", "read_own").validate().is_ok());
+        assert!(rule("", "patients", "read").validate().is_err());
+        assert!(rule("DOCTOR", "patients, users", "read").validate().is_err());
+        assert!(rule("DOCTOR", "patients", "read all").validate().is_err());
+        assert!(rule("DOCTOR", &"x".repeat(129), "read").validate().is_err());
+    }
+
+    #[test]
+    fn test_role_assignment_validation() {
+        let assignment = |member: &str, role: &str| RoleAssignment {
+            member: member.to_string(),
+            role: role.to_string(),
+        };
+        assert!(assignment("SENIOR_DOCTOR", "DOCTOR").validate().is_ok());
+        assert!(assignment("DOCTOR", "DOCTOR").validate().is_err());
+        assert!(assignment("", "DOCTOR").validate().is_err());
+    }
+
+    #[test]
+    fn test_from_rule() {
+        let values = vec!["DOCTOR".to_string(), "patients".to_string(), "read".to_string()];
+        assert_eq!(
+            PolicyRule::from_rule(&values),
+            Some(rule("DOCTOR", "patients", "read"))
+        );
+        assert_eq!(PolicyRule::from_rule(&values[..2]), None);
+        assert_eq!(
+            RoleAssignment::from_rule(&values[..2]),
+            Some(RoleAssignment {
+                member: "DOCTOR".to_string(),
+                role: "patients".to_string(),
+            })
+        );
+    }
+}
@@ -0,0 +1,181 @@
+/*!
+ * Batch Operation Model
+ *
+ * A batch carries several operations in one request (e.g. rescheduling a
+ * provider's appointments after they called in sick) and gets one result
+ * per operation, in order.
+ *
+ * By default each operation is applied on its own, so some may fail while
+ * the others succeed. A transactional batch applies all of them or none:
+ * the first failure rolls back the operations before it and the ones after
+ * it are not attempted.
+ */
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::Validate;
+
+use super::appointment::{
+    CancelAppointmentRequest, CreateAppointmentRequest, UpdateAppointmentRequest,
+};
+
+/// Most operations a batch can carry
+pub const MAX_BATCH_OPERATIONS: usize = 100;
+
+/// Request to run a batch of operations
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct BatchRequest {
+    /// Apply all operations or none
+    #[serde(default)]
+    pub transactional: bool,
+
+    #[validate(length(
+        min = 1,
+        max = MAX_BATCH_OPERATIONS,
+        message = "A batch must contain between 1 and 100 operations"
+    ))]
+    pub operations: Vec<BatchOperation>,
+}
+
+/// One operation of a batch, same body as the equivalent single request
+///
+/// `send_notification` is ignored: batches send no emails.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum BatchOperation {
+    /// POST /appointments
+    CreateAppointment { data: CreateAppointmentRequest },
+    /// PUT /appointments/{id}
+    UpdateAppointment {
+        id: Uuid,
+        data: UpdateAppointmentRequest,
+    },
+    /// POST /appointments/{id}/cancel
+    CancelAppointment {
+        id: Uuid,
+        data: CancelAppointmentRequest,
+    },
+}
+
+impl BatchOperation {
+    /// Appointment permission the operation requires
+    pub fn permission_action(&self) -> &'static str {
+        match self {
+            BatchOperation::CreateAppointment { .. } => "create",
+            BatchOperation::UpdateAppointment { .. } => "update",
+            BatchOperation::CancelAppointment { .. } => "delete",
+        }
+    }
+
+    /// Whether the operation asks to overbook (needs the `overbook`
+    /// permission as well)
+    pub fn overbooks(&self) -> bool {
+        match self {
+            BatchOperation::CreateAppointment { data } => data.overbook,
+            BatchOperation::UpdateAppointment { data, .. } => data.overbook,
+            BatchOperation::CancelAppointment { .. } => false,
+        }
+    }
+}
+
+/// Result of one operation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchOperationResult {
+    /// Position of the operation in the request
+    pub index: usize,
+    /// HTTP status the equivalent single request would have returned
+    pub status: u16,
+    /// Response body the equivalent single request would have returned
+    pub body: serde_json::Value,
+}
+
+/// Response to a batch request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchResponse {
+    pub transactional: bool,
+    /// Whether the changes of a transactional batch were saved (for other
+    /// batches, whether any operation succeeded)
+    pub committed: bool,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub results: Vec<BatchOperationResult>,
+}
+
+impl BatchResponse {
+    /// Build the response from the results of all operations
+    pub fn new(transactional: bool, results: Vec<BatchOperationResult>) -> Self {
+        let succeeded = results.iter().filter(|r| r.status < 400).count();
+        let failed = results.len() - succeeded;
+
+        Self {
+            transactional,
+            committed: succeeded > 0 && (!transactional || failed == 0),
+            succeeded,
+            failed,
+            results,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(index: usize, status: u16) -> BatchOperationResult {
+        BatchOperationResult {
+            index,
+            status,
+            body: serde_json::Value::Null,
+        }
+    }
+
+    #[test]
+    fn test_batch_operation_deserialize() {
+        let request: BatchRequest = serde_json::from_value(serde_json::json!({
+            "transactional": true,
+            "operations": [
+                {
+                    "op": "update_appointment",
+                    "id": "4c3f8f3e-6f7e-4c4e-9a53-2f1b7c0d9e11",
+                    "data": { "scheduled_start": "2026-05-04T09:00:00Z", "overbook": true }
+                },
+                {
+                    "op": "cancel_appointment",
+                    "id": "4c3f8f3e-6f7e-4c4e-9a53-2f1b7c0d9e12",
+                    "data": { "cancellation_reason": "Provider unavailable" }
+                }
+            ]
+        }))
+        .unwrap();
+
+        assert!(request.transactional);
+        assert!(request.validate().is_ok());
+        assert_eq!(request.operations[0].permission_action(), "update");
+        assert_eq!(request.operations[1].permission_action(), "delete");
+        assert!(request.operations[0].overbooks());
+        assert!(!request.operations[1].overbooks());
+    }
+
+    #[test]
+    fn test_batch_request_requires_operations() {
+        let request: BatchRequest =
+            serde_json::from_value(serde_json::json!({ "operations": [] })).unwrap();
+
+        assert!(!request.transactional);
+        assert!(request.validate().is_err());
+    }
+
+    #[test]
+    fn test_batch_response_counts() {
+        let response = BatchResponse::new(false, vec![result(0, 200), result(1, 409)]);
+        assert_eq!((response.succeeded, response.failed), (1, 1));
+        assert!(response.committed);
+
+        let response = BatchResponse::new(true, vec![result(0, 424), result(1, 409)]);
+        assert_eq!((response.succeeded, response.failed), (0, 2));
+        assert!(!response.committed);
+
+        let response = BatchResponse::new(true, vec![result(0, 200), result(1, 201)]);
+        assert!(response.committed);
+    }
+}
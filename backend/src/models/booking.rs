@@ -0,0 +1,326 @@
+/*!
+ * Online Booking Model
+ *
+ * Patients book appointments themselves through the public booking API
+ * (`/api/booking/v1`). Every offered slot carries a signed slot token
+ * (`BookingSlotClaims`); a booking request names one, passes a CAPTCHA and
+ * is confirmed from a link emailed to the patient, after which it becomes a
+ * PENDING appointment for staff to accept.
+ */
+
+use chrono::{DateTime, NaiveDate, Utc};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::models::appointment::{AppointmentStatus, AppointmentType};
+use crate::models::patient::validate_phone;
+
+/// Setting that turns online booking on
+pub const BOOKING_ENABLED_SETTING_KEY: &str = "booking.enabled";
+
+/// Setting with the doctors offered for online booking (empty: all)
+pub const BOOKING_PROVIDERS_SETTING_KEY: &str = "booking.provider_ids";
+
+/// Setting with the appointment types patients may book
+pub const BOOKING_TYPES_SETTING_KEY: &str = "booking.appointment_types";
+
+/// Setting with the minimum notice, in hours
+pub const BOOKING_MIN_NOTICE_SETTING_KEY: &str = "booking.min_notice_hours";
+
+/// Setting with the booking horizon, in days
+pub const BOOKING_MAX_DAYS_SETTING_KEY: &str = "booking.max_days_ahead";
+
+/// Validity of a slot token; slots are offered again after that
+pub const BOOKING_SLOT_TOKEN_MINUTES: i64 = 60;
+
+/// Validity of the verification link of a booking request
+pub const BOOKING_VERIFICATION_MINUTES: i64 = 30;
+
+/// Most days of slots returned by one request
+pub const MAX_BOOKING_SLOT_DAYS: i64 = 14;
+
+/// Most booking requests per email address per hour
+pub const MAX_BOOKING_REQUESTS_PER_EMAIL: i64 = 3;
+
+/// Online booking settings (`booking.*`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct BookingSettings {
+    pub enabled: bool,
+    /// Bookable doctors; empty means every active doctor
+    pub provider_ids: Vec<Uuid>,
+    pub appointment_types: Vec<AppointmentType>,
+    pub min_notice_hours: i64,
+    pub max_days_ahead: i64,
+}
+
+impl Default for BookingSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            provider_ids: Vec::new(),
+            appointment_types: vec![
+                AppointmentType::NewPatient,
+                AppointmentType::FollowUp,
+                AppointmentType::Consultation,
+                AppointmentType::RoutineCheckup,
+            ],
+            min_notice_hours: 24,
+            max_days_ahead: 60,
+        }
+    }
+}
+
+impl BookingSettings {
+    /// Whether patients may book this appointment type
+    pub fn allows_type(&self, appointment_type: AppointmentType) -> bool {
+        self.appointment_types.contains(&appointment_type)
+    }
+
+    /// Whether a slot starting at `start` may be booked at `now`
+    pub fn allows_start(&self, start: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+        start >= now + chrono::Duration::hours(self.min_notice_hours)
+            && start.date_naive()
+                <= (now + chrono::Duration::days(self.max_days_ahead)).date_naive()
+    }
+}
+
+/// Generate a booking verification token (32 random bytes, hex)
+pub fn generate_booking_verification_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// Hash a booking verification token for storage/lookup
+pub fn hash_booking_verification_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.trim().as_bytes()))
+}
+
+/// Doctor offered for online booking
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct BookingProvider {
+    pub id: Uuid,
+    pub first_name: String,
+    pub last_name: String,
+}
+
+impl BookingProvider {
+    /// Name shown to patients
+    pub fn display_name(&self) -> String {
+        format!("Dr. {} {}", self.first_name, self.last_name)
+    }
+}
+
+/// Slot named by a still-valid slot token
+#[derive(Debug, Clone)]
+pub struct OfferedSlot {
+    pub provider: BookingProvider,
+    pub start: DateTime<Utc>,
+    pub duration_minutes: i32,
+    pub appointment_type: AppointmentType,
+}
+
+/// Booking request row (patient details encrypted)
+#[derive(Debug, Clone, FromRow)]
+pub struct BookingRequest {
+    pub id: Uuid,
+    pub provider_id: Uuid,
+    pub scheduled_start: DateTime<Utc>,
+    pub duration_minutes: i32,
+    pub appointment_type: String,
+    pub first_name: String,     // 🔒 Encrypted
+    pub last_name: String,      // 🔒 Encrypted
+    pub date_of_birth: String,  // 🔒 Encrypted
+    pub email: String,          // 🔒 Encrypted
+    pub phone: Option<String>,  // 🔒 Encrypted
+    pub reason: Option<String>, // 🔒 Encrypted
+}
+
+/// Appointment type offered online
+#[derive(Debug, Clone, Serialize)]
+pub struct BookableAppointmentType {
+    #[serde(rename = "type")]
+    pub appointment_type: AppointmentType,
+    pub duration_minutes: i32,
+}
+
+/// Bookable doctor as shown to patients
+#[derive(Debug, Clone, Serialize)]
+pub struct BookingProviderResponse {
+    pub id: Uuid,
+    pub name: String,
+}
+
+impl From<&BookingProvider> for BookingProviderResponse {
+    fn from(provider: &BookingProvider) -> Self {
+        Self {
+            id: provider.id,
+            name: provider.display_name(),
+        }
+    }
+}
+
+/// CAPTCHA widget configuration for the booking form
+#[derive(Debug, Clone, Serialize)]
+pub struct BookingCaptchaConfig {
+    pub provider: &'static str,
+    pub site_key: String,
+}
+
+/// Result of GET /api/booking/v1/options
+#[derive(Debug, Clone, Serialize)]
+pub struct BookingOptionsResponse {
+    pub appointment_types: Vec<BookableAppointmentType>,
+    pub providers: Vec<BookingProviderResponse>,
+    pub min_notice_hours: i64,
+    pub max_days_ahead: i64,
+    pub captcha: BookingCaptchaConfig,
+}
+
+/// Query parameters of GET /api/booking/v1/slots
+#[derive(Debug, Clone, Deserialize)]
+pub struct BookingSlotsQuery {
+    #[serde(rename = "type")]
+    pub appointment_type: AppointmentType,
+    /// First day (default: today)
+    pub from: Option<NaiveDate>,
+    /// Number of days (default and maximum: `MAX_BOOKING_SLOT_DAYS`)
+    pub days: Option<i64>,
+    /// Only this doctor's slots
+    pub provider_id: Option<Uuid>,
+}
+
+/// Slot offered for online booking
+#[derive(Debug, Clone, Serialize)]
+pub struct BookableSlot {
+    pub provider_id: Uuid,
+    pub provider_name: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// Names the slot in the booking request
+    pub slot_token: String,
+}
+
+/// Result of GET /api/booking/v1/slots
+#[derive(Debug, Clone, Serialize)]
+pub struct BookingSlotsResponse {
+    #[serde(rename = "type")]
+    pub appointment_type: AppointmentType,
+    pub duration_minutes: i32,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub slots: Vec<BookableSlot>,
+}
+
+/// Request body for POST /api/booking/v1/requests
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct CreateBookingRequest {
+    #[validate(length(min = 1, max = 2048, message = "Invalid slot"))]
+    pub slot_token: String,
+    #[validate(length(min = 1, max = 100))]
+    pub first_name: String,
+    #[validate(length(min = 1, max = 100))]
+    pub last_name: String,
+    pub date_of_birth: NaiveDate,
+    #[validate(email(message = "Invalid email address"))]
+    pub email: String,
+    #[validate(custom(function = "validate_phone"))]
+    pub phone: Option<String>,
+    #[validate(length(max = 500))]
+    pub reason: Option<String>,
+    pub captcha_token: Option<String>,
+}
+
+/// Result of POST /api/booking/v1/requests
+#[derive(Debug, Clone, Serialize)]
+pub struct BookingRequestAccepted {
+    pub message: String,
+    /// Minutes the verification link stays valid
+    pub expires_in_minutes: i64,
+}
+
+/// Request body for POST /api/booking/v1/requests/verify
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct VerifyBookingRequest {
+    #[validate(length(min = 1, max = 128, message = "Invalid verification link"))]
+    pub token: String,
+}
+
+/// Result of POST /api/booking/v1/requests/verify
+#[derive(Debug, Clone, Serialize)]
+pub struct BookingConfirmationResponse {
+    pub status: AppointmentStatus,
+    pub scheduled_start: DateTime<Utc>,
+    pub scheduled_end: DateTime<Utc>,
+    #[serde(rename = "type")]
+    pub appointment_type: AppointmentType,
+    pub provider_name: String,
+    pub message: String,
+}
+
+/// Outcome of a verified booking, for the audit log
+#[derive(Debug, Clone, Copy)]
+pub struct BookingOutcome {
+    pub booking_request_id: Uuid,
+    pub appointment_id: Uuid,
+    pub patient_id: Uuid,
+    /// Whether the patient record was created for this booking
+    pub new_patient: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    #[test]
+    fn test_booking_settings_window() {
+        let settings = BookingSettings::default();
+        let now = Utc.with_ymd_and_hms(2026, 5, 4, 9, 0, 0).unwrap();
+
+        assert!(!settings.allows_start(now + Duration::hours(2), now));
+        assert!(settings.allows_start(now + Duration::hours(24), now));
+        assert!(settings.allows_start(now + Duration::days(60), now));
+        assert!(!settings.allows_start(now + Duration::days(61), now));
+
+        assert!(settings.allows_type(AppointmentType::FollowUp));
+        assert!(!settings.allows_type(AppointmentType::Urgent));
+    }
+
+    #[test]
+    fn test_verification_token_hash() {
+        let token = generate_booking_verification_token();
+        assert_eq!(token.len(), 64);
+        assert_eq!(
+            hash_booking_verification_token(&token),
+            hash_booking_verification_token(&format!("{}\n", token))
+        );
+        assert_ne!(hash_booking_verification_token(&token), token);
+    }
+
+    #[test]
+    fn test_create_booking_request_validation() {
+        let request: CreateBookingRequest = serde_json::from_str(
+            r#"{
+                "slot_token": "abc",
+                "first_name": "Anna",
+                "last_name": "Rossi",
+                "date_of_birth": "1980-04-12",
+                "email": "anna.rossi@example.com",
+                "phone": "+39 333 1234567"
+            }"#,
+        )
+        .unwrap();
+        assert!(request.validate().is_ok());
+
+        let invalid = CreateBookingRequest {
+            email: "not-an-email".to_string(),
+            ..request
+        };
+        assert!(invalid.validate().is_err());
+    }
+}
@@ -0,0 +1,275 @@
+/*!
+ * Branding Model
+ *
+ * Data models for white-label practice theming.
+ *
+ * Branding values live in `system_settings` under the `branding` group:
+ * - Colors are stored as hex strings (#RRGGBB)
+ * - Image assets (logo variants, email header) are stored in `uploaded_files`
+ *   with purpose BRANDING and referenced from settings by file ID
+ *
+ * The resulting metadata is public and consumed by the frontend theme as well
+ * as the email and document renderers.
+ */
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
+
+/// Default primary UI color (matches the frontend's default theme)
+pub const DEFAULT_PRIMARY_COLOR: &str = "#2563eb";
+
+/// Default accent color for generated documents
+pub const DEFAULT_DOCUMENT_ACCENT_COLOR: &str = "#1e40af";
+
+/// Setting key for the primary UI color
+pub const PRIMARY_COLOR_KEY: &str = "branding.primary_color";
+
+/// Setting key for the document accent color
+pub const DOCUMENT_ACCENT_COLOR_KEY: &str = "branding.document_accent_color";
+
+/// Setting key for the externally reachable base URL
+pub const PUBLIC_BASE_URL_KEY: &str = "branding.public_base_url";
+
+// ============================================================================
+// Enums
+// ============================================================================
+
+/// Branding image asset slot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BrandingAsset {
+    /// Logo for light backgrounds
+    LogoLight,
+    /// Logo for dark backgrounds
+    LogoDark,
+    /// Compact/square logo (sidebar, favicon)
+    LogoCompact,
+    /// Banner shown at the top of outgoing emails
+    EmailHeader,
+}
+
+impl BrandingAsset {
+    /// Setting key that stores the uploaded file ID for this asset
+    pub fn setting_key(&self) -> &'static str {
+        match self {
+            BrandingAsset::LogoLight => "branding.logo_light_file_id",
+            BrandingAsset::LogoDark => "branding.logo_dark_file_id",
+            BrandingAsset::LogoCompact => "branding.logo_compact_file_id",
+            BrandingAsset::EmailHeader => "branding.email_header_file_id",
+        }
+    }
+
+    /// URL path segment used by the asset endpoints
+    pub fn as_path(&self) -> &'static str {
+        match self {
+            BrandingAsset::LogoLight => "logo-light",
+            BrandingAsset::LogoDark => "logo-dark",
+            BrandingAsset::LogoCompact => "logo-compact",
+            BrandingAsset::EmailHeader => "email-header",
+        }
+    }
+
+    /// Parse from URL path segment
+    pub fn from_path(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "logo-light" => Some(BrandingAsset::LogoLight),
+            "logo-dark" => Some(BrandingAsset::LogoDark),
+            "logo-compact" => Some(BrandingAsset::LogoCompact),
+            "email-header" => Some(BrandingAsset::EmailHeader),
+            _ => None,
+        }
+    }
+
+    /// Public URL path serving this asset
+    pub fn public_url(&self) -> String {
+        format!("/api/v1/branding/assets/{}", self.as_path())
+    }
+
+    /// All asset slots
+    pub fn all() -> Vec<Self> {
+        vec![
+            BrandingAsset::LogoLight,
+            BrandingAsset::LogoDark,
+            BrandingAsset::LogoCompact,
+            BrandingAsset::EmailHeader,
+        ]
+    }
+}
+
+impl std::fmt::Display for BrandingAsset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_path())
+    }
+}
+
+// ============================================================================
+// Response DTOs
+// ============================================================================
+
+/// Logo URLs by variant (None when the variant is not configured)
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BrandingLogos {
+    /// Main practice logo (uploaded via /settings/logo)
+    pub primary: Option<String>,
+    pub light: Option<String>,
+    pub dark: Option<String>,
+    pub compact: Option<String>,
+}
+
+/// Public branding metadata
+///
+/// GET /api/v1/branding
+#[derive(Debug, Clone, Serialize)]
+pub struct BrandingResponse {
+    pub practice_name: String,
+    pub primary_color: String,
+    pub document_accent_color: String,
+    pub logos: BrandingLogos,
+    pub email_header_url: Option<String>,
+}
+
+/// Raw branding values as stored in settings
+#[derive(Debug, Clone, Default)]
+pub struct BrandingSettings {
+    pub practice_name: Option<String>,
+    pub primary_color: Option<String>,
+    pub document_accent_color: Option<String>,
+    pub public_base_url: Option<String>,
+    pub logo_light_file_id: Option<Uuid>,
+    pub logo_dark_file_id: Option<Uuid>,
+    pub logo_compact_file_id: Option<Uuid>,
+    pub email_header_file_id: Option<Uuid>,
+}
+
+impl BrandingSettings {
+    /// Get the configured file ID for an asset slot
+    pub fn asset_file_id(&self, asset: BrandingAsset) -> Option<Uuid> {
+        match asset {
+            BrandingAsset::LogoLight => self.logo_light_file_id,
+            BrandingAsset::LogoDark => self.logo_dark_file_id,
+            BrandingAsset::LogoCompact => self.logo_compact_file_id,
+            BrandingAsset::EmailHeader => self.email_header_file_id,
+        }
+    }
+
+    /// Primary color, falling back to the default
+    pub fn primary_color(&self) -> String {
+        self.primary_color
+            .clone()
+            .unwrap_or_else(|| DEFAULT_PRIMARY_COLOR.to_string())
+    }
+
+    /// Document accent color, falling back to the default
+    pub fn document_accent_color(&self) -> String {
+        self.document_accent_color
+            .clone()
+            .unwrap_or_else(|| DEFAULT_DOCUMENT_ACCENT_COLOR.to_string())
+    }
+
+    /// Absolute URL for an asset, if both the asset and the public base URL are configured
+    ///
+    /// Email clients cannot resolve relative paths, so emails need this form.
+    pub fn absolute_asset_url(&self, asset: BrandingAsset) -> Option<String> {
+        self.asset_file_id(asset)?;
+        let base = self.public_base_url.as_deref()?.trim_end_matches('/');
+        Some(format!("{}{}", base, asset.public_url()))
+    }
+}
+
+// ============================================================================
+// Request DTOs
+// ============================================================================
+
+/// Update branding colors/base URL request
+///
+/// PUT /api/v1/branding
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct UpdateBrandingRequest {
+    #[validate(custom(function = "validate_hex_color"))]
+    pub primary_color: Option<String>,
+
+    #[validate(custom(function = "validate_hex_color"))]
+    pub document_accent_color: Option<String>,
+
+    #[validate(url(message = "Public base URL must be a valid URL"))]
+    pub public_base_url: Option<String>,
+}
+
+/// Validator function for hex color fields (#RRGGBB)
+pub fn validate_hex_color(value: &str) -> Result<(), ValidationError> {
+    let is_valid = value.len() == 7
+        && value.starts_with('#')
+        && value[1..].chars().all(|c| c.is_ascii_hexdigit());
+
+    if is_valid {
+        Ok(())
+    } else {
+        let mut error = ValidationError::new("invalid_hex_color");
+        error.message = Some(
+            format!("Invalid color '{}'. Expected hex format #RRGGBB", value).into(),
+        );
+        Err(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_branding_asset_path_roundtrip() {
+        for asset in BrandingAsset::all() {
+            assert_eq!(BrandingAsset::from_path(asset.as_path()), Some(asset));
+        }
+        assert_eq!(BrandingAsset::from_path("LOGO-DARK"), Some(BrandingAsset::LogoDark));
+        assert_eq!(BrandingAsset::from_path("favicon"), None);
+    }
+
+    #[test]
+    fn test_branding_asset_setting_keys_are_valid() {
+        for asset in BrandingAsset::all() {
+            let key = asset.setting_key();
+            assert!(key.starts_with("branding."));
+            assert!(key
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '.' || c == '_'));
+        }
+    }
+
+    #[test]
+    fn test_validate_hex_color() {
+        assert!(validate_hex_color("#2563eb").is_ok());
+        assert!(validate_hex_color("#ABCDEF").is_ok());
+        assert!(validate_hex_color("2563eb").is_err());
+        assert!(validate_hex_color("#2563e").is_err());
+        assert!(validate_hex_color("#zzzzzz").is_err());
+        assert!(validate_hex_color("red").is_err());
+    }
+
+    #[test]
+    fn test_absolute_asset_url() {
+        let mut settings = BrandingSettings {
+            email_header_file_id: Some(Uuid::new_v4()),
+            ..Default::default()
+        };
+        // No base URL configured
+        assert_eq!(settings.absolute_asset_url(BrandingAsset::EmailHeader), None);
+
+        settings.public_base_url = Some("https://studio.example.com/".to_string());
+        assert_eq!(
+            settings.absolute_asset_url(BrandingAsset::EmailHeader),
+            Some("https://studio.example.com/api/v1/branding/assets/email-header".to_string())
+        );
+
+        // Asset not configured
+        assert_eq!(settings.absolute_asset_url(BrandingAsset::LogoDark), None);
+    }
+
+    #[test]
+    fn test_color_fallbacks() {
+        let settings = BrandingSettings::default();
+        assert_eq!(settings.primary_color(), DEFAULT_PRIMARY_COLOR);
+        assert_eq!(settings.document_accent_color(), DEFAULT_DOCUMENT_ACCENT_COLOR);
+    }
+}
@@ -0,0 +1,338 @@
+/*!
+ * Calendar Feed Models
+ *
+ * A provider's appointments as a read-only iCalendar (RFC 5545) feed that
+ * phone and desktop calendars subscribe to. Calendar apps cannot send a
+ * JWT: the feed URL carries a random 256-bit token, of which only the
+ * SHA-256 hash is stored in `calendar_feeds.token_hash`.
+ *
+ * The privacy mode decides what each event shows: the patient, type and
+ * reason of the appointment (FULL), or only that the provider is busy
+ * (BUSY_ONLY).
+ */
+
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use super::{AppointmentDto, AppointmentStatus, AppointmentType};
+
+/// Days of past appointments in a feed
+pub const FEED_PAST_DAYS: i64 = 30;
+
+/// Days of upcoming appointments in a feed
+pub const FEED_FUTURE_DAYS: i64 = 365;
+
+/// What the events of a feed show
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "VARCHAR", rename_all = "SCREAMING_SNAKE_CASE")]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum CalendarPrivacyMode {
+    /// Patient, type and reason of each appointment
+    Full,
+    /// Only the times the provider is busy
+    BusyOnly,
+}
+
+/// Calendar feed of a provider (API output; the token is never returned
+/// again after creation)
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct CalendarFeed {
+    pub provider_id: Uuid,
+    #[serde(skip_serializing)]
+    pub token_hash: String,
+    pub privacy_mode: CalendarPrivacyMode,
+    pub last_accessed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Request body for POST and PUT /api/v1/appointments/calendar-feed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalendarFeedRequest {
+    pub privacy_mode: CalendarPrivacyMode,
+}
+
+/// Response of POST /api/v1/appointments/calendar-feed
+#[derive(Debug, Clone, Serialize)]
+pub struct CreatedCalendarFeed {
+    #[serde(flatten)]
+    pub feed: CalendarFeed,
+    /// Shown only once; the feed URL is `path` on the API host
+    pub token: String,
+    pub path: String,
+}
+
+/// Generate a new feed token (64 hex chars)
+pub fn generate_calendar_feed_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// Hash a feed token for storage/lookup
+pub fn hash_calendar_feed_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.trim().as_bytes()))
+}
+
+/// Path of the feed with the given token
+pub fn calendar_feed_path(token: &str) -> String {
+    format!("/api/v1/calendar/{}.ics", token)
+}
+
+/// Appointment as a calendar event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CalendarEvent {
+    pub uid: Uuid,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub summary: String,
+    pub description: Option<String>,
+    /// Not confirmed yet (PENDING or SCHEDULED)
+    pub tentative: bool,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl CalendarEvent {
+    /// Event for an appointment, showing as much as `privacy_mode` allows
+    ///
+    /// `patient_name` is only used in FULL mode.
+    pub fn from_appointment(
+        appointment: &AppointmentDto,
+        patient_name: Option<&str>,
+        privacy_mode: CalendarPrivacyMode,
+    ) -> Self {
+        let (summary, description) = match privacy_mode {
+            CalendarPrivacyMode::BusyOnly => ("Busy".to_string(), None),
+            CalendarPrivacyMode::Full => {
+                let type_label = type_label(appointment.appointment_type);
+                let summary = match patient_name {
+                    Some(name) => format!("{} - {}", name, type_label),
+                    None => type_label.to_string(),
+                };
+                (summary, appointment.reason.clone())
+            }
+        };
+
+        Self {
+            uid: appointment.id,
+            start: appointment.scheduled_start,
+            end: appointment.scheduled_end,
+            summary,
+            description,
+            tentative: matches!(
+                appointment.status,
+                AppointmentStatus::Pending | AppointmentStatus::Scheduled
+            ),
+            updated_at: appointment.updated_at,
+        }
+    }
+}
+
+/// Name of an appointment type in event titles
+fn type_label(appointment_type: AppointmentType) -> &'static str {
+    match appointment_type {
+        AppointmentType::NewPatient => "New patient",
+        AppointmentType::FollowUp => "Follow-up",
+        AppointmentType::Urgent => "Urgent",
+        AppointmentType::Consultation => "Consultation",
+        AppointmentType::RoutineCheckup => "Routine checkup",
+        AppointmentType::Acupuncture => "Acupuncture",
+        AppointmentType::Televisit => "Televisit",
+    }
+}
+
+/// Render a calendar named `name` with the given events
+pub fn render_calendar(name: &str, events: &[CalendarEvent]) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//DocPat//Calendar Feed//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        "METHOD:PUBLISH".to_string(),
+        format!("X-WR-CALNAME:{}", escape_text(name)),
+        // Ask calendar apps to refresh every 15 minutes
+        "REFRESH-INTERVAL;VALUE=DURATION:PT15M".to_string(),
+        "X-PUBLISHED-TTL:PT15M".to_string(),
+    ];
+
+    for event in events {
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!("UID:{}@docpat", event.uid));
+        lines.push(format!("DTSTAMP:{}", format_utc(event.updated_at)));
+        lines.push(format!("LAST-MODIFIED:{}", format_utc(event.updated_at)));
+        lines.push(format!("DTSTART:{}", format_utc(event.start)));
+        lines.push(format!("DTEND:{}", format_utc(event.end)));
+        lines.push(format!("SUMMARY:{}", escape_text(&event.summary)));
+        if let Some(description) = &event.description {
+            lines.push(format!("DESCRIPTION:{}", escape_text(description)));
+        }
+        let status = if event.tentative {
+            "TENTATIVE"
+        } else {
+            "CONFIRMED"
+        };
+        lines.push(format!("STATUS:{}", status));
+        lines.push("TRANSP:OPAQUE".to_string());
+        lines.push("END:VEVENT".to_string());
+    }
+
+    lines.push("END:VCALENDAR".to_string());
+
+    lines.iter().map(|line| fold_line(line)).collect()
+}
+
+/// UTC date-time in iCalendar form (20261016T080000Z)
+fn format_utc(time: DateTime<Utc>) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Escape a TEXT value: backslashes, semicolons, commas and newlines
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Fold a content line at 75 octets, without splitting a character, and end
+/// it with CRLF
+fn fold_line(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + 8);
+    let mut octets = 0;
+    for c in line.chars() {
+        // Continuation lines start with a space, which counts
+        if octets + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            octets = 1;
+        }
+        folded.push(c);
+        octets += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    fn appointment(status: AppointmentStatus) -> AppointmentDto {
+        let start = Utc.with_ymd_and_hms(2026, 5, 12, 8, 0, 0).unwrap();
+        AppointmentDto {
+            id: Uuid::new_v4(),
+            patient_id: Uuid::new_v4(),
+            provider_id: Uuid::new_v4(),
+            scheduled_start: start,
+            scheduled_end: start + Duration::minutes(30),
+            duration_minutes: 30,
+            appointment_type: AppointmentType::FollowUp,
+            reason: Some("Blood pressure, check-up".to_string()),
+            notes: Some("Private note".to_string()),
+            status,
+            cancellation_reason: None,
+            cancelled_at: None,
+            confirmation_code: None,
+            confirmed_at: None,
+            is_recurring: false,
+            recurring_pattern: None,
+            parent_appointment_id: None,
+            reminder_sent_email: false,
+            reminder_sent_sms: false,
+            reminder_sent_whatsapp: false,
+            checked_in_at: None,
+            visit_started_at: None,
+            checked_out_at: None,
+            created_at: start,
+            updated_at: start,
+        }
+    }
+
+    #[test]
+    fn test_calendar_feed_token_hash() {
+        let token = generate_calendar_feed_token();
+        assert_eq!(token.len(), 64);
+        assert_ne!(token, generate_calendar_feed_token());
+        assert_eq!(
+            hash_calendar_feed_token(&token),
+            hash_calendar_feed_token(&format!(" {} ", token))
+        );
+        assert_eq!(
+            calendar_feed_path("abc"),
+            "/api/v1/calendar/abc.ics".to_string()
+        );
+    }
+
+    #[test]
+    fn test_event_privacy_modes() {
+        let appt = appointment(AppointmentStatus::Confirmed);
+
+        let full =
+            CalendarEvent::from_appointment(&appt, Some("Anna Bianchi"), CalendarPrivacyMode::Full);
+        assert_eq!(full.summary, "Anna Bianchi - Follow-up");
+        assert_eq!(
+            full.description.as_deref(),
+            Some("Blood pressure, check-up")
+        );
+        assert!(!full.tentative);
+
+        let busy = CalendarEvent::from_appointment(
+            &appt,
+            Some("Anna Bianchi"),
+            CalendarPrivacyMode::BusyOnly,
+        );
+        assert_eq!(busy.summary, "Busy");
+        assert_eq!(busy.description, None);
+
+        let pending = appointment(AppointmentStatus::Pending);
+        assert!(
+            CalendarEvent::from_appointment(&pending, None, CalendarPrivacyMode::BusyOnly)
+                .tentative
+        );
+    }
+
+    #[test]
+    fn test_render_calendar() {
+        let appt = appointment(AppointmentStatus::Scheduled);
+        let event =
+            CalendarEvent::from_appointment(&appt, Some("Anna Bianchi"), CalendarPrivacyMode::Full);
+        let ics = render_calendar("Dr. Mario Rossi", &[event]);
+
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+        assert!(ics.contains(&format!("UID:{}@docpat\r\n", appt.id)));
+        assert!(ics.contains("DTSTART:20260512T080000Z\r\n"));
+        assert!(ics.contains("DTEND:20260512T083000Z\r\n"));
+        assert!(ics.contains("DESCRIPTION:Blood pressure\\, check-up\r\n"));
+        assert!(ics.contains("STATUS:TENTATIVE\r\n"));
+        assert!(!ics.contains("Private note"));
+
+        let empty = render_calendar("Dr. Mario Rossi", &[]);
+        assert!(!empty.contains("BEGIN:VEVENT"));
+    }
+
+    #[test]
+    fn test_escape_and_fold() {
+        assert_eq!(escape_text("a;b,c\\d\r\ne"), "a\\;b\\,c\\\\d\\ne");
+
+        let long = format!("SUMMARY:{}", "è".repeat(60));
+        let folded = fold_line(&long);
+        assert!(folded.ends_with("\r\n"));
+        for line in folded.trim_end_matches("\r\n").split("\r\n") {
+            assert!(line.len() <= 75);
+        }
+        assert_eq!(folded.replace("\r\n ", "").trim_end(), long);
+    }
+}
@@ -22,6 +22,7 @@ pub enum DocumentType {
     LabRequest,
     VisitSummary,
     Prescription,
+    SsnPrescription,
     Custom,
 }
 
@@ -34,6 +35,7 @@ impl DocumentType {
             DocumentType::LabRequest => "LAB_REQUEST",
             DocumentType::VisitSummary => "VISIT_SUMMARY",
             DocumentType::Prescription => "PRESCRIPTION",
+            DocumentType::SsnPrescription => "SSN_PRESCRIPTION",
             DocumentType::Custom => "CUSTOM",
         }
     }
@@ -46,6 +48,7 @@ impl DocumentType {
             "LAB_REQUEST" => Some(DocumentType::LabRequest),
             "VISIT_SUMMARY" => Some(DocumentType::VisitSummary),
             "PRESCRIPTION" => Some(DocumentType::Prescription),
+            "SSN_PRESCRIPTION" => Some(DocumentType::SsnPrescription),
             "CUSTOM" => Some(DocumentType::Custom),
             _ => None,
         }
@@ -59,6 +62,7 @@ impl DocumentType {
             DocumentType::LabRequest => "Lab Request",
             DocumentType::VisitSummary => "Visit Summary",
             DocumentType::Prescription => "Prescription",
+            DocumentType::SsnPrescription => "SSN Prescription",
             DocumentType::Custom => "Custom Document",
         }
     }
@@ -518,6 +522,16 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_document_type_ssn_prescription() {
+        assert_eq!(DocumentType::SsnPrescription.as_str(), "SSN_PRESCRIPTION");
+        assert_eq!(
+            DocumentType::from_str("SSN_PRESCRIPTION"),
+            Some(DocumentType::SsnPrescription)
+        );
+        assert_eq!(DocumentType::SsnPrescription.display_name(), "SSN Prescription");
+    }
+
     #[test]
     fn test_document_type_custom() {
         assert_eq!(DocumentType::Custom.as_str(), "CUSTOM");
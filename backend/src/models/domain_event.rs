@@ -0,0 +1,444 @@
+/*!
+ * Domain Events
+ *
+ * Structured audit records for mutating API calls, emitted automatically by
+ * `audit_middleware` instead of hand-written `AuditLog::create` calls in every
+ * handler. An event names the entity, the operation, the actor and client, and
+ * the change: the resulting record and, when the handler recorded the prior
+ * state with `record_before`, a field-level before/after diff.
+ *
+ * A handler that writes its own, more specific audit row suppresses the
+ * automatic event for that request (see `mark_explicit_audit`). Routes that
+ * must not be audited this way (credentials, read-only POSTs) opt out with
+ * the `skip_domain_events` layer.
+ */
+
+use ipnetwork::IpNetwork;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use sqlx::PgPool;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+use super::audit_log::{AuditAction, EntityType};
+use crate::services::siem_exporter::{self, SecurityEvent, SecurityEventKind};
+
+/// Replacement for values of sensitive keys
+const REDACTED: &str = "[REDACTED]";
+
+/// Keys whose values never end up in an event (at any depth)
+const SENSITIVE_KEYS: &[&str] = &[
+    "password",
+    "current_password",
+    "new_password",
+    "password_hash",
+    "access_token",
+    "refresh_token",
+    "token",
+    "mfa_secret",
+    "secret",
+    "backup_codes",
+    "totp_code",
+    "signature_data",
+];
+
+/// Keys that change on every write and are left out of diffs
+const VOLATILE_KEYS: &[&str] = &["updated_at"];
+
+tokio::task_local! {
+    /// Domain event state of the request currently executing
+    static CURRENT_DOMAIN_EVENT: DomainEventRecorder;
+}
+
+#[derive(Debug, Default)]
+struct RecorderState {
+    /// A handler wrote its own audit row
+    explicit: bool,
+    /// Entity state before the change
+    before: Option<Value>,
+}
+
+/// Collects what handlers report about a mutating request
+#[derive(Debug, Clone, Default)]
+pub struct DomainEventRecorder {
+    state: Arc<Mutex<RecorderState>>,
+}
+
+impl DomainEventRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether a handler wrote its own audit row
+    pub fn has_explicit_audit(&self) -> bool {
+        self.state.lock().map(|s| s.explicit).unwrap_or(false)
+    }
+
+    /// State recorded before the change, if any
+    pub fn take_before(&self) -> Option<Value> {
+        self.state.lock().ok().and_then(|mut s| s.before.take())
+    }
+}
+
+/// Run a future with a domain event recorder in scope
+pub async fn with_domain_event_scope<F>(recorder: DomainEventRecorder, future: F) -> F::Output
+where
+    F: Future,
+{
+    CURRENT_DOMAIN_EVENT.scope(recorder, future).await
+}
+
+/// Record the entity state before it is changed (enables the diff)
+///
+/// No-op outside a mutating request.
+pub fn record_before<T: Serialize>(entity: &T) {
+    let Ok(value) = serde_json::to_value(entity) else {
+        return;
+    };
+    let _ = CURRENT_DOMAIN_EVENT.try_with(|recorder| {
+        if let Ok(mut state) = recorder.state.lock() {
+            state.before = Some(value);
+        }
+    });
+}
+
+/// Note that the current request wrote its own audit row
+///
+/// Called by `AuditLog::create`, so the automatic event is not duplicated.
+pub fn mark_explicit_audit() {
+    let _ = CURRENT_DOMAIN_EVENT.try_with(|recorder| {
+        if let Ok(mut state) = recorder.state.lock() {
+            state.explicit = true;
+        }
+    });
+}
+
+/// Response extension marking a route as exempt from automatic events
+#[derive(Debug, Clone, Copy)]
+pub struct DomainEventOptOut;
+
+/// What a mutating request did, derived from its method and path
+#[derive(Debug, Clone, PartialEq)]
+pub struct DomainEventTarget {
+    /// API resource (first path segment after `/api/v1/`)
+    pub resource: String,
+    pub entity_id: Option<String>,
+    /// `created`, `updated`, `deleted`, or the action segment (`sign`, `cancel`, ...)
+    pub operation: String,
+    pub action: AuditAction,
+}
+
+/// Whether a path segment identifies an entity (UUID or number)
+fn is_entity_id(segment: &str) -> bool {
+    Uuid::parse_str(segment).is_ok()
+        || (!segment.is_empty() && segment.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Path below the API version prefix (`/api/v1/` or `/api/v2/`)
+pub(crate) fn strip_api_prefix(path: &str) -> Option<&str> {
+    path.strip_prefix("/api/v1/").or_else(|| path.strip_prefix("/api/v2/"))
+}
+
+impl DomainEventTarget {
+    /// Describe a mutating request (`POST`/`PUT`/`PATCH`/`DELETE` on `/api/v1/...` or `/api/v2/...`)
+    ///
+    /// Examples:
+    /// - `POST /api/v1/holidays` -> holidays, created
+    /// - `PUT /api/v1/holidays/{id}` -> holidays/{id}, updated
+    /// - `POST /api/v1/visits/{id}/sign` -> visits/{id}, sign
+    pub fn from_request(method: &str, path: &str) -> Option<Self> {
+        let segments: Vec<&str> = strip_api_prefix(path)?
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .collect();
+        let (resource, rest) = segments.split_first()?;
+
+        let entity_position = rest.iter().position(|s| is_entity_id(s));
+        let entity_id = entity_position.map(|i| rest[i].to_string());
+
+        // Action endpoints: a non-ID segment after the entity ID
+        let action_segment = entity_position
+            .and_then(|i| rest.last().filter(|_| i + 1 < rest.len()))
+            .map(|s| s.replace('-', "_"));
+
+        let (operation, action) = match (method, action_segment) {
+            ("DELETE", _) => ("deleted".to_string(), AuditAction::Delete),
+            (_, Some(segment)) => (segment, AuditAction::Update),
+            ("POST", None) => ("created".to_string(), AuditAction::Create),
+            ("PUT" | "PATCH", None) => ("updated".to_string(), AuditAction::Update),
+            _ => return None,
+        };
+
+        Some(Self {
+            resource: resource.to_string(),
+            entity_id,
+            operation,
+            action,
+        })
+    }
+
+    /// Audit entity type for the resource
+    pub fn entity_type(&self) -> String {
+        let entity_type = match self.resource.as_str() {
+            "patients" => EntityType::Patient,
+            "appointments" => EntityType::Appointment,
+            "visits" => EntityType::Visit,
+            "diagnoses" => EntityType::Diagnosis,
+            "prescriptions" => EntityType::Prescription,
+            "users" => EntityType::User,
+            "documents" => EntityType::Document,
+            "holidays" => EntityType::Holiday,
+            "working-hours" => EntityType::WorkingHours,
+            "settings" | "branding" => EntityType::SystemSetting,
+            "files" => EntityType::File,
+            "visit-templates" | "prescription-templates" | "document-templates" => {
+                EntityType::Template
+            }
+            "notifications" => EntityType::Notification,
+            "reports" => EntityType::Report,
+            "delegations" => EntityType::AccessDelegation,
+            "authorization" => EntityType::AuthorizationPolicy,
+            "retention-rules" => EntityType::RetentionRule,
+            "consent-texts" => EntityType::Consent,
+            other => return other.replace('-', "_").to_uppercase(),
+        };
+        entity_type.to_string()
+    }
+}
+
+/// Replace values of sensitive keys, recursively
+pub fn scrub(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, nested) in map.iter_mut() {
+                if SENSITIVE_KEYS.contains(&key.as_str()) {
+                    *nested = Value::String(REDACTED.to_string());
+                } else {
+                    scrub(nested);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(scrub),
+        _ => {}
+    }
+}
+
+/// Field-level diff of two JSON objects: `{ field: { "old": .., "new": .. } }`
+///
+/// Only top-level fields are compared; nested values are reported whole.
+pub fn diff(before: &Value, after: &Value) -> Value {
+    let empty = Map::new();
+    let before = before.as_object().unwrap_or(&empty);
+    let after = after.as_object().unwrap_or(&empty);
+
+    let mut changes = Map::new();
+    for key in before.keys().chain(after.keys()) {
+        if VOLATILE_KEYS.contains(&key.as_str()) || changes.contains_key(key) {
+            continue;
+        }
+        let old = before.get(key).unwrap_or(&Value::Null);
+        let new = after.get(key).unwrap_or(&Value::Null);
+        if old != new {
+            changes.insert(
+                key.clone(),
+                serde_json::json!({ "old": old, "new": new }),
+            );
+        }
+    }
+    Value::Object(changes)
+}
+
+/// Domain event ready to be written to `audit_logs`
+#[derive(Debug, Clone)]
+pub struct DomainEvent {
+    pub target: DomainEventTarget,
+    pub user_id: Option<Uuid>,
+    pub impersonator_id: Option<Uuid>,
+    pub ip_address: Option<IpNetwork>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<Uuid>,
+    /// Entity state before the change (from `record_before`)
+    pub before: Option<Value>,
+    /// Entity state after the change (the JSON response body)
+    pub after: Option<Value>,
+}
+
+impl DomainEvent {
+    /// Entity ID from the path, or the `id` of the created record
+    fn entity_id(&self) -> Option<String> {
+        self.target.entity_id.clone().or_else(|| {
+            self.after
+                .as_ref()
+                .and_then(|after| after.get("id"))
+                .map(|id| match id {
+                    Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                })
+        })
+    }
+
+    /// `changes` payload: event name plus diff or snapshots
+    pub fn changes(&self) -> Value {
+        let mut changes = Map::new();
+        changes.insert(
+            "event".to_string(),
+            Value::String(format!("{}.{}", self.target.resource, self.target.operation)),
+        );
+
+        let mut before = self.before.clone();
+        let mut after = self.after.clone();
+        before.iter_mut().for_each(scrub);
+        after.iter_mut().for_each(scrub);
+
+        match (before, after) {
+            (Some(before), Some(after)) => {
+                changes.insert("diff".to_string(), diff(&before, &after));
+            }
+            (Some(before), None) => {
+                changes.insert("before".to_string(), before);
+            }
+            (None, Some(after)) => {
+                changes.insert("after".to_string(), after);
+            }
+            (None, None) => {}
+        }
+
+        Value::Object(changes)
+    }
+
+    /// Write the event to `audit_logs`
+    pub async fn save(&self, pool: &PgPool) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO audit_logs (
+                user_id, action, entity_type, entity_id,
+                changes, ip_address, user_agent, request_id, impersonator_id
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            "#,
+        )
+        .bind(self.user_id)
+        .bind(self.target.action.to_string())
+        .bind(self.target.entity_type())
+        .bind(self.entity_id())
+        .bind(self.changes())
+        .bind(self.ip_address)
+        .bind(&self.user_agent)
+        .bind(self.request_id)
+        .bind(self.impersonator_id)
+        .execute(pool)
+        .await?;
+
+        if siem_exporter::is_enabled() {
+            let name = format!("{}.{}", self.target.resource, self.target.operation);
+            siem_exporter::emit(SecurityEvent {
+                user_id: self.user_id,
+                ip_address: self.ip_address.map(|ip| ip.ip().to_string()),
+                request_id: self.request_id,
+                entity_type: Some(self.target.entity_type()),
+                entity_id: self.entity_id(),
+                ..SecurityEvent::new(SecurityEventKind::Audit, name.clone(), name)
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_target_from_request() {
+        let id = Uuid::new_v4();
+
+        let target = DomainEventTarget::from_request("POST", "/api/v1/holidays").unwrap();
+        assert_eq!(target.resource, "holidays");
+        assert_eq!(target.entity_id, None);
+        assert_eq!(target.operation, "created");
+        assert_eq!(target.entity_type(), "HOLIDAY");
+
+        let target =
+            DomainEventTarget::from_request("PUT", &format!("/api/v1/working-hours/overrides/{}", id))
+                .unwrap();
+        assert_eq!(target.entity_id, Some(id.to_string()));
+        assert_eq!(target.operation, "updated");
+        assert_eq!(target.entity_type(), "WORKING_HOURS");
+
+        let target =
+            DomainEventTarget::from_request("POST", &format!("/api/v1/visits/{}/sign", id)).unwrap();
+        assert_eq!(target.operation, "sign");
+        assert_eq!(target.action, AuditAction::Update);
+
+        let target =
+            DomainEventTarget::from_request("POST", &format!("/api/v1/users/{}/reset-mfa", id)).unwrap();
+        assert_eq!(target.operation, "reset_mfa");
+
+        let target = DomainEventTarget::from_request("DELETE", &format!("/api/v1/files/{}", id)).unwrap();
+        assert_eq!(target.operation, "deleted");
+        assert_eq!(target.action, AuditAction::Delete);
+
+        let target = DomainEventTarget::from_request("POST", "/api/v2/holidays").unwrap();
+        assert_eq!(target.resource, "holidays");
+        assert_eq!(target.operation, "created");
+
+        assert_eq!(DomainEventTarget::from_request("GET", "/api/v1/patients"), None);
+        assert_eq!(DomainEventTarget::from_request("POST", "/health"), None);
+    }
+
+    #[test]
+    fn test_diff_and_scrub() {
+        let before = json!({ "name": "Natale", "is_recurring": false, "updated_at": "a" });
+        let after = json!({ "name": "Natale", "is_recurring": true, "updated_at": "b" });
+        assert_eq!(
+            diff(&before, &after),
+            json!({ "is_recurring": { "old": false, "new": true } })
+        );
+
+        let mut body = json!({ "user": { "email": "a@b.c", "password": "x" }, "tokens": [{ "refresh_token": "y" }] });
+        scrub(&mut body);
+        assert_eq!(
+            body,
+            json!({ "user": { "email": "a@b.c", "password": "[REDACTED]" }, "tokens": [{ "refresh_token": "[REDACTED]" }] })
+        );
+    }
+
+    #[test]
+    fn test_event_changes() {
+        let id = Uuid::new_v4();
+        let event = DomainEvent {
+            target: DomainEventTarget::from_request("POST", "/api/v1/holidays").unwrap(),
+            user_id: None,
+            impersonator_id: None,
+            ip_address: None,
+            user_agent: None,
+            request_id: None,
+            before: None,
+            after: Some(json!({ "id": id, "name": "Natale" })),
+        };
+        assert_eq!(event.entity_id(), Some(id.to_string()));
+        assert_eq!(
+            event.changes(),
+            json!({ "event": "holidays.created", "after": { "id": id, "name": "Natale" } })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_recorder_scope() {
+        let recorder = DomainEventRecorder::new();
+        with_domain_event_scope(recorder.clone(), async {
+            record_before(&json!({ "name": "old" }));
+            mark_explicit_audit();
+        })
+        .await;
+        assert!(recorder.has_explicit_audit());
+        assert_eq!(recorder.take_before(), Some(json!({ "name": "old" })));
+
+        // Outside a scope these are no-ops
+        record_before(&json!({}));
+        mark_explicit_audit();
+    }
+}
@@ -0,0 +1,232 @@
+/*!
+ * E-Prescription Model
+ *
+ * Italian dematerialized prescriptions (ricetta dematerializzata) issued
+ * through Sistema TS. A submitted prescription receives its NRE (Numero
+ * Ricetta Elettronica), stored in `prescriptions.e_prescription_id`; its
+ * status then follows the pharmacy's dispensing through status callbacks.
+ * Every submission, callback and cancellation is kept as an event.
+ */
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::models::{GeneratedDocumentResponse, PrescriptionResponse, TemplateLanguage};
+
+/// Status of a dematerialized prescription at Sistema TS
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum EPrescriptionStatus {
+    /// Accepted by Sistema TS, NRE assigned
+    Issued,
+    /// Refused by Sistema TS (no NRE)
+    Rejected,
+    /// Taken in charge by a pharmacy (presa in carico)
+    InCharge,
+    /// Some of the packages were dispensed
+    PartiallyDispensed,
+    /// All packages were dispensed (erogata)
+    Dispensed,
+    /// Cancelled by the prescriber (annullata)
+    Cancelled,
+    /// Not dispensed within its validity
+    Expired,
+}
+
+impl EPrescriptionStatus {
+    /// Convert to database string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EPrescriptionStatus::Issued => "ISSUED",
+            EPrescriptionStatus::Rejected => "REJECTED",
+            EPrescriptionStatus::InCharge => "IN_CHARGE",
+            EPrescriptionStatus::PartiallyDispensed => "PARTIALLY_DISPENSED",
+            EPrescriptionStatus::Dispensed => "DISPENSED",
+            EPrescriptionStatus::Cancelled => "CANCELLED",
+            EPrescriptionStatus::Expired => "EXPIRED",
+        }
+    }
+
+    /// Parse the database string representation
+    pub fn from_db(value: &str) -> Option<Self> {
+        match value {
+            "ISSUED" => Some(EPrescriptionStatus::Issued),
+            "REJECTED" => Some(EPrescriptionStatus::Rejected),
+            "IN_CHARGE" => Some(EPrescriptionStatus::InCharge),
+            "PARTIALLY_DISPENSED" => Some(EPrescriptionStatus::PartiallyDispensed),
+            "DISPENSED" => Some(EPrescriptionStatus::Dispensed),
+            "CANCELLED" => Some(EPrescriptionStatus::Cancelled),
+            "EXPIRED" => Some(EPrescriptionStatus::Expired),
+            _ => None,
+        }
+    }
+
+    /// Whether the NRE is still open at the pharmacy
+    pub fn is_open(&self) -> bool {
+        matches!(
+            self,
+            EPrescriptionStatus::Issued
+                | EPrescriptionStatus::InCharge
+                | EPrescriptionStatus::PartiallyDispensed
+        )
+    }
+
+    /// Whether a status callback can move an NRE from this status to `next`
+    ///
+    /// Only open NREs change; Issued and Rejected are set by submission,
+    /// never by a callback.
+    pub fn can_transition_to(&self, next: EPrescriptionStatus) -> bool {
+        use EPrescriptionStatus::*;
+        self.is_open()
+            && matches!(
+                next,
+                InCharge | PartiallyDispensed | Dispensed | Cancelled | Expired
+            )
+    }
+}
+
+/// What recorded an e-prescription event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum EPrescriptionEventSource {
+    /// Submission to Sistema TS (Issued or Rejected)
+    Submission,
+    /// Status callback
+    Callback,
+    /// Cancellation requested by the prescriber
+    Cancellation,
+}
+
+impl EPrescriptionEventSource {
+    /// Convert to database string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EPrescriptionEventSource::Submission => "SUBMISSION",
+            EPrescriptionEventSource::Callback => "CALLBACK",
+            EPrescriptionEventSource::Cancellation => "CANCELLATION",
+        }
+    }
+}
+
+/// E-prescription event row
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct EPrescriptionEvent {
+    pub id: Uuid,
+    pub prescription_id: Uuid,
+    /// NULL for rejected submissions
+    pub nre: Option<String>,
+    pub status: String,
+    pub source: String,
+    /// Sistema TS error messages or the callback detail
+    pub detail: Option<String>,
+    /// Submitted AIC and exemption codes (submission events only)
+    pub aic_code: Option<String>,
+    pub exemption_code: Option<String>,
+    /// When the event happened at Sistema TS or the pharmacy
+    pub occurred_at: DateTime<Utc>,
+    /// NULL for callbacks
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// E-prescription state of a prescription (API output)
+#[derive(Debug, Clone, Serialize)]
+pub struct EPrescriptionResponse {
+    pub prescription_id: Uuid,
+    pub nre: Option<String>,
+    pub status: Option<String>,
+    pub sent_at: Option<DateTime<Utc>>,
+    /// Newest first
+    pub events: Vec<EPrescriptionEvent>,
+}
+
+/// Request body for POST /api/v1/prescriptions/:id/e-prescription
+#[derive(Debug, Clone, Default, Deserialize, Validate)]
+pub struct SubmitEPrescriptionRequest {
+    /// AIC code of the package; defaults to the AIC code of the medication
+    /// with the prescription's name
+    #[validate(length(min = 6, max = 9, message = "AIC code must be 6-9 digits"))]
+    pub aic_code: Option<String>,
+    /// Patient exemption code (codice esenzione)
+    #[validate(length(min = 1, max = 6, message = "Exemption code must be 1-6 characters"))]
+    pub exemption_code: Option<String>,
+}
+
+/// Status callback body (POST /api/v1/e-prescriptions/callback)
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct EPrescriptionCallback {
+    #[validate(length(min = 1, max = 20, message = "NRE must be 1-20 characters"))]
+    pub nre: String,
+    pub status: EPrescriptionStatus,
+    pub occurred_at: Option<DateTime<Utc>>,
+    #[validate(length(max = 1000, message = "Detail too long (max 1000 chars)"))]
+    pub detail: Option<String>,
+}
+
+/// Request body for POST /api/v1/prescriptions/:id/e-prescription/promemoria
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct GenerateNrePromemoriaRequest {
+    /// PRESCRIPTION template to use; defaults to the NRE promemoria template
+    pub template_id: Option<Uuid>,
+    #[serde(default)]
+    pub language: TemplateLanguage,
+}
+
+/// What the promemoria of an issued NRE prints
+#[derive(Debug, Clone)]
+pub struct NrePromemoria {
+    pub prescription: PrescriptionResponse,
+    pub nre: String,
+    pub issued_at: DateTime<Utc>,
+    pub patient_fiscal_code: String,
+    pub aic_code: Option<String>,
+    pub exemption_code: Option<String>,
+    pub quantity: i32,
+    /// From the Sistema TS configuration, if still configured
+    pub prescriber_fiscal_code: Option<String>,
+}
+
+/// Generated promemoria and the e-prescription it was printed for (API output)
+#[derive(Debug, Clone, Serialize)]
+pub struct NrePromemoriaResponse {
+    pub e_prescription: EPrescriptionResponse,
+    pub document: GeneratedDocumentResponse,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_round_trip() {
+        use EPrescriptionStatus::*;
+        for status in [
+            Issued,
+            Rejected,
+            InCharge,
+            PartiallyDispensed,
+            Dispensed,
+            Cancelled,
+            Expired,
+        ] {
+            assert_eq!(EPrescriptionStatus::from_db(status.as_str()), Some(status));
+        }
+        assert_eq!(EPrescriptionStatus::from_db("SENT"), None);
+    }
+
+    #[test]
+    fn test_callback_transitions() {
+        use EPrescriptionStatus::*;
+        assert!(Issued.can_transition_to(InCharge));
+        assert!(InCharge.can_transition_to(Dispensed));
+        assert!(PartiallyDispensed.can_transition_to(Dispensed));
+        assert!(Issued.can_transition_to(Expired));
+        assert!(!Issued.can_transition_to(Issued));
+        assert!(!Issued.can_transition_to(Rejected));
+        assert!(!Dispensed.can_transition_to(Cancelled));
+        assert!(!Rejected.can_transition_to(InCharge));
+    }
+}
@@ -55,17 +55,21 @@ impl DocumentStatus {
                 new_status,
                 DocumentStatus::Generated | DocumentStatus::Failed
             ),
-            DocumentStatus::Generated => {
-                matches!(new_status, DocumentStatus::Delivered | DocumentStatus::Deleted)
-            }
+            // FAILED is reached when the stored file turns out to be missing
+            DocumentStatus::Generated => matches!(
+                new_status,
+                DocumentStatus::Delivered | DocumentStatus::Deleted | DocumentStatus::Failed
+            ),
             DocumentStatus::Delivered => matches!(new_status, DocumentStatus::Deleted),
-            DocumentStatus::Failed | DocumentStatus::Deleted => false, // Terminal states
+            // FAILED can only be retried (POST /documents/:id/retry)
+            DocumentStatus::Failed => matches!(new_status, DocumentStatus::Generating),
+            DocumentStatus::Deleted => false, // Terminal state
         }
     }
 
     /// Check if this is a terminal state (cannot be changed)
     pub fn is_terminal(&self) -> bool {
-        matches!(self, DocumentStatus::Failed | DocumentStatus::Deleted)
+        matches!(self, DocumentStatus::Deleted)
     }
 }
 
@@ -249,6 +253,105 @@ pub struct SignDocumentRequest {
     pub confirmation_code: Option<String>,
 }
 
+/// Template used to render patient chart print bundles
+pub const PRINT_BUNDLE_TEMPLATE_KEY: &str = "patient_chart_bundle_it";
+
+/// Template used to render visit and full chart clinical summaries
+pub const CLINICAL_SUMMARY_TEMPLATE_KEY: &str = "clinical_summary_it";
+
+/// Number of recent visits included when the request does not specify one
+pub const DEFAULT_BUNDLE_VISIT_LIMIT: i64 = 5;
+
+/// Section of the patient chart that can be included in a print bundle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ChartSection {
+    Demographics,
+    ActiveProblems,
+    RecentVisits,
+    CurrentMedications,
+    LatestLabs,
+}
+
+impl ChartSection {
+    /// All sections in default print order
+    pub const ALL: [ChartSection; 5] = [
+        ChartSection::Demographics,
+        ChartSection::ActiveProblems,
+        ChartSection::RecentVisits,
+        ChartSection::CurrentMedications,
+        ChartSection::LatestLabs,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChartSection::Demographics => "DEMOGRAPHICS",
+            ChartSection::ActiveProblems => "ACTIVE_PROBLEMS",
+            ChartSection::RecentVisits => "RECENT_VISITS",
+            ChartSection::CurrentMedications => "CURRENT_MEDICATIONS",
+            ChartSection::LatestLabs => "LATEST_LABS",
+        }
+    }
+}
+
+/// Request to print a bundle of patient chart sections into one PDF
+///
+/// POST /api/v1/patients/:id/print-bundle
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Validate)]
+pub struct CreatePrintBundleRequest {
+    /// Sections in print order (default: all sections)
+    #[serde(default)]
+    pub sections: Vec<ChartSection>,
+
+    /// Number of recent visits to include (default 5)
+    #[validate(range(min = 1, max = 20, message = "Visit limit must be between 1 and 20"))]
+    pub visit_limit: Option<i64>,
+
+    #[validate(length(
+        min = 1,
+        max = 255,
+        message = "Document title must be 1-255 characters"
+    ))]
+    pub document_title: Option<String>,
+
+    /// Reason for the bundle, printed on the first page
+    /// (e.g. "Referral to cardiology", "Hospital admission")
+    #[validate(length(max = 500, message = "Purpose must be at most 500 characters"))]
+    pub purpose: Option<String>,
+}
+
+impl CreatePrintBundleRequest {
+    /// Requested sections without duplicates, or all sections if none given
+    pub fn resolved_sections(&self) -> Vec<ChartSection> {
+        if self.sections.is_empty() {
+            return ChartSection::ALL.to_vec();
+        }
+
+        let mut sections = Vec::with_capacity(self.sections.len());
+        for section in &self.sections {
+            if !sections.contains(section) {
+                sections.push(*section);
+            }
+        }
+        sections
+    }
+
+    pub fn resolved_visit_limit(&self) -> i64 {
+        self.visit_limit.unwrap_or(DEFAULT_BUNDLE_VISIT_LIMIT)
+    }
+}
+
+/// Original request a document was generated from
+///
+/// Stored encrypted in `generated_documents.generation_request` so that a
+/// FAILED generation can be re-run once the underlying issue is fixed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum GenerationRequest {
+    Generate(GenerateDocumentRequest),
+    PrintBundle(CreatePrintBundleRequest),
+}
+
 /// Summary of a generated document (for listings)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GeneratedDocumentSummary {
@@ -259,6 +362,7 @@ pub struct GeneratedDocumentSummary {
     pub document_title: String,
     pub document_filename: String,
     pub status: DocumentStatus,
+    pub generation_error: Option<String>,
     pub is_signed: bool,
     pub file_size_bytes: Option<i64>,
     pub created_at: DateTime<Utc>,
@@ -275,6 +379,7 @@ impl From<GeneratedDocument> for GeneratedDocumentSummary {
             document_title: doc.document_title,
             document_filename: doc.document_filename,
             status: DocumentStatus::from_str(&doc.status).unwrap_or(DocumentStatus::Generated),
+            generation_error: doc.generation_error,
             is_signed: doc.is_signed.unwrap_or(false),
             file_size_bytes: doc.file_size_bytes,
             created_at: doc.created_at,
@@ -379,14 +484,17 @@ mod tests {
         // From GENERATED
         assert!(DocumentStatus::Generated.can_transition_to(DocumentStatus::Delivered));
         assert!(DocumentStatus::Generated.can_transition_to(DocumentStatus::Deleted));
-        assert!(!DocumentStatus::Generated.can_transition_to(DocumentStatus::Failed));
+        assert!(DocumentStatus::Generated.can_transition_to(DocumentStatus::Failed));
 
         // From DELIVERED
         assert!(DocumentStatus::Delivered.can_transition_to(DocumentStatus::Deleted));
         assert!(!DocumentStatus::Delivered.can_transition_to(DocumentStatus::Generated));
 
-        // Terminal states
+        // From FAILED (retry only)
+        assert!(DocumentStatus::Failed.can_transition_to(DocumentStatus::Generating));
         assert!(!DocumentStatus::Failed.can_transition_to(DocumentStatus::Generated));
+
+        // Terminal state
         assert!(!DocumentStatus::Deleted.can_transition_to(DocumentStatus::Generated));
     }
 
@@ -406,8 +514,8 @@ mod tests {
     }
 
     #[test]
-    fn test_failed_cannot_transition_to_any() {
-        assert!(!DocumentStatus::Failed.can_transition_to(DocumentStatus::Generating));
+    fn test_failed_can_only_transition_to_generating() {
+        assert!(DocumentStatus::Failed.can_transition_to(DocumentStatus::Generating));
         assert!(!DocumentStatus::Failed.can_transition_to(DocumentStatus::Generated));
         assert!(!DocumentStatus::Failed.can_transition_to(DocumentStatus::Delivered));
         assert!(!DocumentStatus::Failed.can_transition_to(DocumentStatus::Deleted));
@@ -430,7 +538,7 @@ mod tests {
         assert!(!DocumentStatus::Generating.is_terminal());
         assert!(!DocumentStatus::Generated.is_terminal());
         assert!(!DocumentStatus::Delivered.is_terminal());
-        assert!(DocumentStatus::Failed.is_terminal());
+        assert!(!DocumentStatus::Failed.is_terminal());
         assert!(DocumentStatus::Deleted.is_terminal());
     }
 
@@ -597,6 +705,56 @@ mod tests {
         assert_eq!(count.count, 30);
     }
 
+    // ==================== Print Bundle Tests ====================
+
+    #[test]
+    fn test_print_bundle_defaults_to_all_sections() {
+        let request = CreatePrintBundleRequest::default();
+        assert_eq!(request.resolved_sections(), ChartSection::ALL.to_vec());
+        assert_eq!(request.resolved_visit_limit(), DEFAULT_BUNDLE_VISIT_LIMIT);
+    }
+
+    #[test]
+    fn test_print_bundle_keeps_order_and_drops_duplicates() {
+        let request: CreatePrintBundleRequest = serde_json::from_value(serde_json::json!({
+            "sections": ["CURRENT_MEDICATIONS", "DEMOGRAPHICS", "CURRENT_MEDICATIONS"],
+            "visit_limit": 3
+        }))
+        .unwrap();
+
+        assert_eq!(
+            request.resolved_sections(),
+            vec![ChartSection::CurrentMedications, ChartSection::Demographics]
+        );
+        assert_eq!(request.resolved_visit_limit(), 3);
+    }
+
+    #[test]
+    fn test_print_bundle_validation() {
+        let request = CreatePrintBundleRequest {
+            visit_limit: Some(50),
+            ..Default::default()
+        };
+        assert!(request.validate().is_err());
+
+        let request = CreatePrintBundleRequest {
+            visit_limit: Some(10),
+            ..Default::default()
+        };
+        assert!(request.validate().is_ok());
+    }
+
+    #[test]
+    fn test_chart_section_serialization() {
+        for section in ChartSection::ALL {
+            assert_eq!(
+                serde_json::to_value(section).unwrap(),
+                serde_json::json!(section.as_str())
+            );
+        }
+        assert!(serde_json::from_str::<ChartSection>("\"BILLING\"").is_err());
+    }
+
     // ==================== Edge Cases ====================
 
     #[test]
@@ -0,0 +1,400 @@
+/*!
+ * Imaging Order Model
+ *
+ * Diagnostic imaging requested for a patient (modality, body part, clinical
+ * question, urgency), optionally at a visit. An order moves from ORDERED to
+ * PERFORMED to REPORTED, and can be printed as a referral letter for the
+ * radiology facility. The clinical question and the report are encrypted.
+ */
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::models::{GeneratedDocumentResponse, TemplateLanguage};
+use crate::utils::encryption::EncryptionKey;
+
+/// Imaging technique
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ImagingModality {
+    Xray,
+    Ct,
+    Mri,
+    Ultrasound,
+    Mammography,
+    Dexa,
+    Pet,
+    NuclearMedicine,
+    Other,
+}
+
+impl ImagingModality {
+    /// Convert to database string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImagingModality::Xray => "XRAY",
+            ImagingModality::Ct => "CT",
+            ImagingModality::Mri => "MRI",
+            ImagingModality::Ultrasound => "ULTRASOUND",
+            ImagingModality::Mammography => "MAMMOGRAPHY",
+            ImagingModality::Dexa => "DEXA",
+            ImagingModality::Pet => "PET",
+            ImagingModality::NuclearMedicine => "NUCLEAR_MEDICINE",
+            ImagingModality::Other => "OTHER",
+        }
+    }
+
+    /// Parse the database string representation
+    pub fn from_db(value: &str) -> Option<Self> {
+        match value {
+            "XRAY" => Some(ImagingModality::Xray),
+            "CT" => Some(ImagingModality::Ct),
+            "MRI" => Some(ImagingModality::Mri),
+            "ULTRASOUND" => Some(ImagingModality::Ultrasound),
+            "MAMMOGRAPHY" => Some(ImagingModality::Mammography),
+            "DEXA" => Some(ImagingModality::Dexa),
+            "PET" => Some(ImagingModality::Pet),
+            "NUCLEAR_MEDICINE" => Some(ImagingModality::NuclearMedicine),
+            "OTHER" => Some(ImagingModality::Other),
+            _ => None,
+        }
+    }
+
+    /// Name printed on the referral letter
+    pub fn label(&self, language: TemplateLanguage) -> &'static str {
+        let (italian, english) = match self {
+            ImagingModality::Xray => ("Radiografia", "X-ray"),
+            ImagingModality::Ct => ("TC", "CT scan"),
+            ImagingModality::Mri => ("Risonanza magnetica", "MRI"),
+            ImagingModality::Ultrasound => ("Ecografia", "Ultrasound"),
+            ImagingModality::Mammography => ("Mammografia", "Mammography"),
+            ImagingModality::Dexa => ("MOC (DEXA)", "DEXA scan"),
+            ImagingModality::Pet => ("PET", "PET"),
+            ImagingModality::NuclearMedicine => ("Medicina nucleare", "Nuclear medicine"),
+            ImagingModality::Other => ("Altro", "Other"),
+        };
+        match language {
+            TemplateLanguage::Italian => italian,
+            TemplateLanguage::English => english,
+        }
+    }
+}
+
+/// Side of the body imaged, for paired organs and limbs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ImagingLaterality {
+    Left,
+    Right,
+    Bilateral,
+}
+
+impl ImagingLaterality {
+    /// Convert to database string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImagingLaterality::Left => "LEFT",
+            ImagingLaterality::Right => "RIGHT",
+            ImagingLaterality::Bilateral => "BILATERAL",
+        }
+    }
+}
+
+/// Lifecycle of an imaging order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ImagingOrderStatus {
+    Ordered,
+    /// The exam has been done, the report is pending
+    Performed,
+    Reported,
+    Cancelled,
+}
+
+impl ImagingOrderStatus {
+    /// Convert to database string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImagingOrderStatus::Ordered => "ORDERED",
+            ImagingOrderStatus::Performed => "PERFORMED",
+            ImagingOrderStatus::Reported => "REPORTED",
+            ImagingOrderStatus::Cancelled => "CANCELLED",
+        }
+    }
+
+    /// Parse the database string representation
+    pub fn from_db(value: &str) -> Option<Self> {
+        match value {
+            "ORDERED" => Some(ImagingOrderStatus::Ordered),
+            "PERFORMED" => Some(ImagingOrderStatus::Performed),
+            "REPORTED" => Some(ImagingOrderStatus::Reported),
+            "CANCELLED" => Some(ImagingOrderStatus::Cancelled),
+            _ => None,
+        }
+    }
+
+    /// Whether an order can move from this status to `next`
+    ///
+    /// Orders only move forward (an exam can be reported without being
+    /// marked performed first); anything not yet reported can be cancelled.
+    pub fn can_transition_to(&self, next: ImagingOrderStatus) -> bool {
+        use ImagingOrderStatus::*;
+        matches!(
+            (self, next),
+            (Ordered, Performed)
+                | (Ordered, Reported)
+                | (Performed, Reported)
+                | (Ordered, Cancelled)
+                | (Performed, Cancelled)
+        )
+    }
+}
+
+/// How soon the exam should be performed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ImagingUrgency {
+    #[default]
+    Routine,
+    Urgent,
+}
+
+impl ImagingUrgency {
+    /// Convert to database string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImagingUrgency::Routine => "ROUTINE",
+            ImagingUrgency::Urgent => "URGENT",
+        }
+    }
+}
+
+/// Imaging order row (encrypted)
+#[derive(Debug, Clone, FromRow)]
+pub struct ImagingOrder {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub visit_id: Option<Uuid>,
+    pub visit_date: Option<NaiveDate>,
+    pub modality: String,
+    pub body_part: String,
+    pub laterality: Option<String>,
+    pub contrast: bool,
+    pub clinical_question: String, // 🔒 Encrypted
+    pub urgency: String,
+    pub status: String,
+    pub facility: Option<String>,
+    pub performed_at: Option<DateTime<Utc>>,
+    pub reported_at: Option<DateTime<Utc>>,
+    pub report: Option<String>, // 🔒 Encrypted
+    pub referral_document_id: Option<Uuid>,
+    pub ordered_at: DateTime<Utc>,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_by: Option<Uuid>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ImagingOrder {
+    /// Decrypt into the API representation
+    pub fn decrypt(&self, key: &EncryptionKey) -> Result<ImagingOrderResponse> {
+        Ok(ImagingOrderResponse {
+            id: self.id,
+            patient_id: self.patient_id,
+            visit_id: self.visit_id,
+            visit_date: self.visit_date,
+            modality: self.modality.clone(),
+            body_part: self.body_part.clone(),
+            laterality: self.laterality.clone(),
+            contrast: self.contrast,
+            clinical_question: key
+                .decrypt(&self.clinical_question)
+                .context("Failed to decrypt imaging order clinical_question")?,
+            urgency: self.urgency.clone(),
+            status: self.status.clone(),
+            facility: self.facility.clone(),
+            performed_at: self.performed_at,
+            reported_at: self.reported_at,
+            report: key
+                .decrypt_optional(&self.report)
+                .context("Failed to decrypt imaging order report")?,
+            referral_document_id: self.referral_document_id,
+            ordered_at: self.ordered_at,
+            created_by: self.created_by,
+            created_at: self.created_at,
+            updated_by: self.updated_by,
+            updated_at: self.updated_at,
+        })
+    }
+}
+
+/// Imaging order (API output, decrypted)
+#[derive(Debug, Clone, Serialize)]
+pub struct ImagingOrderResponse {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub visit_id: Option<Uuid>,
+    pub visit_date: Option<NaiveDate>,
+    pub modality: String,
+    pub body_part: String,
+    pub laterality: Option<String>,
+    pub contrast: bool,
+    pub clinical_question: String,
+    pub urgency: String,
+    pub status: String,
+    pub facility: Option<String>,
+    pub performed_at: Option<DateTime<Utc>>,
+    pub reported_at: Option<DateTime<Utc>>,
+    pub report: Option<String>,
+    /// Most recently generated referral letter
+    pub referral_document_id: Option<Uuid>,
+    pub ordered_at: DateTime<Utc>,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_by: Option<Uuid>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Request body for POST /api/v1/patients/:id/imaging-orders
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct CreateImagingOrderRequest {
+    /// Visit of the same patient the exam is ordered at
+    pub visit_id: Option<Uuid>,
+    pub modality: ImagingModality,
+    #[validate(length(min = 1, max = 100, message = "Body part must be 1-100 characters"))]
+    pub body_part: String,
+    pub laterality: Option<ImagingLaterality>,
+    #[serde(default)]
+    pub contrast: bool,
+    #[validate(length(
+        min = 1,
+        max = 5000,
+        message = "Clinical question must be 1-5000 characters"
+    ))]
+    pub clinical_question: String,
+    #[serde(default)]
+    pub urgency: ImagingUrgency,
+    #[validate(length(max = 255))]
+    pub facility: Option<String>,
+}
+
+/// Request body for PUT /api/v1/patients/:id/imaging-orders/:order_id
+///
+/// Absent fields are left unchanged. Moving to PERFORMED or REPORTED stamps
+/// `performed_at`/`reported_at` with the current time unless given.
+#[derive(Debug, Clone, Default, Deserialize, Validate)]
+pub struct UpdateImagingOrderRequest {
+    pub status: Option<ImagingOrderStatus>,
+    #[validate(length(min = 1, max = 100, message = "Body part must be 1-100 characters"))]
+    pub body_part: Option<String>,
+    pub laterality: Option<ImagingLaterality>,
+    pub contrast: Option<bool>,
+    #[validate(length(
+        min = 1,
+        max = 5000,
+        message = "Clinical question must be 1-5000 characters"
+    ))]
+    pub clinical_question: Option<String>,
+    pub urgency: Option<ImagingUrgency>,
+    #[validate(length(max = 255))]
+    pub facility: Option<String>,
+    pub performed_at: Option<DateTime<Utc>>,
+    pub reported_at: Option<DateTime<Utc>>,
+    #[validate(length(max = 20000, message = "Report too long (max 20000 chars)"))]
+    pub report: Option<String>,
+}
+
+/// Query parameters for GET /api/v1/patients/:id/imaging-orders
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ListImagingOrdersQuery {
+    pub status: Option<ImagingOrderStatus>,
+    pub modality: Option<ImagingModality>,
+    pub visit_id: Option<Uuid>,
+}
+
+/// Request body for POST /api/v1/patients/:id/imaging-orders/:order_id/referral-letter
+#[derive(Debug, Clone, Default, Deserialize, Validate)]
+pub struct GenerateImagingReferralRequest {
+    /// REFERRAL_LETTER template to use; defaults to the imaging referral
+    /// template (Italian) or the default referral template for `language`
+    pub template_id: Option<Uuid>,
+    #[serde(default)]
+    pub language: TemplateLanguage,
+    /// Printed as the clinical history
+    #[validate(length(max = 5000, message = "Clinical history too long (max 5000 chars)"))]
+    pub clinical_history: Option<String>,
+}
+
+/// Generated referral letter and the order it was generated for (API output)
+#[derive(Debug, Clone, Serialize)]
+pub struct ImagingReferralResponse {
+    pub order: ImagingOrderResponse,
+    pub document: GeneratedDocumentResponse,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_transitions() {
+        use ImagingOrderStatus::*;
+        assert!(Ordered.can_transition_to(Performed));
+        assert!(Performed.can_transition_to(Reported));
+        assert!(Ordered.can_transition_to(Reported));
+        assert!(Performed.can_transition_to(Cancelled));
+
+        assert!(!Reported.can_transition_to(Cancelled));
+        assert!(!Reported.can_transition_to(Performed));
+        assert!(!Performed.can_transition_to(Ordered));
+        assert!(!Cancelled.can_transition_to(Ordered));
+        assert!(!Ordered.can_transition_to(Ordered));
+    }
+
+    #[test]
+    fn test_modality_round_trip() {
+        for modality in [
+            ImagingModality::Xray,
+            ImagingModality::Ct,
+            ImagingModality::Mri,
+            ImagingModality::Ultrasound,
+            ImagingModality::Mammography,
+            ImagingModality::Dexa,
+            ImagingModality::Pet,
+            ImagingModality::NuclearMedicine,
+            ImagingModality::Other,
+        ] {
+            assert_eq!(ImagingModality::from_db(modality.as_str()), Some(modality));
+            let json = serde_json::to_value(modality).unwrap();
+            assert_eq!(json, modality.as_str());
+        }
+        assert_eq!(ImagingModality::from_db("SPECT"), None);
+    }
+
+    #[test]
+    fn test_create_imaging_order_request_validation() {
+        let request: CreateImagingOrderRequest = serde_json::from_str(
+            r#"{"modality": "MRI", "body_part": "Ginocchio", "laterality": "LEFT",
+                "clinical_question": "Sospetta lesione meniscale"}"#,
+        )
+        .unwrap();
+        assert!(request.validate().is_ok());
+        assert_eq!(request.urgency, ImagingUrgency::Routine);
+        assert!(!request.contrast);
+
+        let no_question: CreateImagingOrderRequest = serde_json::from_str(
+            r#"{"modality": "XRAY", "body_part": "Torace", "clinical_question": ""}"#,
+        )
+        .unwrap();
+        assert!(no_question.validate().is_err());
+
+        let unknown = serde_json::from_str::<CreateImagingOrderRequest>(
+            r#"{"modality": "SPECT", "body_part": "Cuore", "clinical_question": "x"}"#,
+        );
+        assert!(unknown.is_err());
+    }
+}
@@ -0,0 +1,147 @@
+/*!
+ * Impersonation Model
+ *
+ * An ADMIN can start a short impersonation session to see the application as
+ * another user (support and troubleshooting). The session uses a dedicated
+ * access token whose `act` claim names the admin; no refresh token is issued.
+ *
+ * While impersonating:
+ * - `jwt_auth_middleware` adds an `Impersonation` extension and the
+ *   `X-Impersonated-By` response header (the frontend shows a banner)
+ * - every `audit_logs` row written for the request records both the
+ *   impersonated user (`user_id`) and the admin (`impersonator_id`), via the
+ *   task-local set by the audit middleware
+ */
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::future::Future;
+use uuid::Uuid;
+
+/// How long an impersonation token stays valid
+pub const IMPERSONATION_MINUTES: i64 = 30;
+
+/// Response header flagging impersonated requests (value: admin user ID)
+pub const IMPERSONATED_BY_HEADER: &str = "x-impersonated-by";
+
+/// Response header with the impersonation session expiry (RFC 3339)
+pub const IMPERSONATION_EXPIRES_HEADER: &str = "x-impersonation-expires";
+
+tokio::task_local! {
+    /// Admin impersonating the user of the request currently executing
+    static CURRENT_IMPERSONATOR: Uuid;
+}
+
+/// Impersonation details for an authenticated request
+///
+/// Added as a request extension by the auth middleware when the access token
+/// carries an `act` claim.
+#[derive(Debug, Clone)]
+pub struct Impersonation {
+    /// Admin acting as the authenticated user
+    pub impersonator_id: Uuid,
+    /// Token ID (identifies the impersonation session)
+    pub jti: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Admin impersonating the current request's user, if any
+pub fn current_impersonator() -> Option<Uuid> {
+    CURRENT_IMPERSONATOR.try_with(|id| *id).ok()
+}
+
+/// Run a future with the impersonating admin in scope
+pub async fn with_impersonator_scope<F>(impersonator_id: Option<Uuid>, future: F) -> F::Output
+where
+    F: Future,
+{
+    match impersonator_id {
+        Some(id) => CURRENT_IMPERSONATOR.scope(id, future).await,
+        None => future.await,
+    }
+}
+
+/// Impersonation session row
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct ImpersonationSession {
+    pub id: Uuid,
+    pub impersonator_id: Uuid,
+    pub target_user_id: Uuid,
+    pub reason: String,
+    #[serde(skip_serializing)]
+    pub token_jti: String,
+    pub started_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+    pub ip_address: Option<String>,
+}
+
+impl ImpersonationSession {
+    /// Expiry for a session started now
+    pub fn expiry_from(now: DateTime<Utc>) -> DateTime<Utc> {
+        now + Duration::minutes(IMPERSONATION_MINUTES)
+    }
+}
+
+/// Request body for starting an impersonation session
+#[derive(Debug, Clone, Deserialize)]
+pub struct StartImpersonationRequest {
+    /// Why the admin needs to act as the user (recorded in the audit log)
+    pub reason: String,
+}
+
+impl StartImpersonationRequest {
+    /// Trimmed reason, or an error if it is missing or too long
+    pub fn validated_reason(&self) -> Result<&str, String> {
+        let reason = self.reason.trim();
+        if reason.is_empty() {
+            return Err("A reason is required to impersonate a user".to_string());
+        }
+        if reason.len() > 500 {
+            return Err("reason cannot exceed 500 characters".to_string());
+        }
+        Ok(reason)
+    }
+}
+
+/// Response for a started impersonation session
+#[derive(Debug, Serialize)]
+pub struct StartImpersonationResponse {
+    pub session_id: Uuid,
+    /// Access token acting as the target user (no refresh token)
+    pub access_token: String,
+    pub expires_in: i64,
+    pub expires_at: DateTime<Utc>,
+    pub user_id: Uuid,
+    pub username: String,
+    pub role: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_impersonator_scope() {
+        assert_eq!(current_impersonator(), None);
+
+        let admin_id = Uuid::new_v4();
+        let inner = with_impersonator_scope(Some(admin_id), async { current_impersonator() }).await;
+        assert_eq!(inner, Some(admin_id));
+
+        let inner = with_impersonator_scope(None, async { current_impersonator() }).await;
+        assert_eq!(inner, None);
+        assert_eq!(current_impersonator(), None);
+    }
+
+    #[test]
+    fn test_validated_reason() {
+        let req = |reason: &str| StartImpersonationRequest {
+            reason: reason.to_string(),
+        };
+        assert_eq!(req("  Ticket #42 ").validated_reason(), Ok("Ticket #42"));
+        assert!(req("   ").validated_reason().is_err());
+        assert!(req(&"x".repeat(501)).validated_reason().is_err());
+    }
+}
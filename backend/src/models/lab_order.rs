@@ -0,0 +1,282 @@
+/*!
+ * Lab Order Model
+ *
+ * Laboratory tests requested for a patient, optionally at a visit. Each
+ * requested test is LOINC coded; results are recorded separately
+ * (`lab_result`) and may point back to the order, moving it from ORDERED to
+ * PARTIAL to COMPLETED. Tests are picked from a LOINC-coded catalog, and an
+ * order can be printed as a requisition with a barcoded number. Clinical
+ * information for the lab is encrypted.
+ */
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::models::{GeneratedDocumentResponse, TemplateLanguage};
+use crate::utils::encryption::EncryptionKey;
+
+/// Lifecycle of a lab order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum LabOrderStatus {
+    Ordered,
+    /// Some, but not all, requested tests have a result
+    Partial,
+    Completed,
+    Cancelled,
+}
+
+impl LabOrderStatus {
+    /// Convert to database string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LabOrderStatus::Ordered => "ORDERED",
+            LabOrderStatus::Partial => "PARTIAL",
+            LabOrderStatus::Completed => "COMPLETED",
+            LabOrderStatus::Cancelled => "CANCELLED",
+        }
+    }
+}
+
+/// How soon the lab should process the order
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum LabUrgency {
+    #[default]
+    Routine,
+    Urgent,
+}
+
+impl LabUrgency {
+    /// Convert to database string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LabUrgency::Routine => "ROUTINE",
+            LabUrgency::Urgent => "URGENT",
+        }
+    }
+}
+
+/// Lab order row (encrypted)
+#[derive(Debug, Clone, FromRow)]
+pub struct LabOrder {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub visit_id: Option<Uuid>,
+    pub visit_date: Option<NaiveDate>,
+    pub status: String,
+    pub urgency: String,
+    pub fasting: bool,
+    pub lab_name: Option<String>,
+    pub clinical_info: Option<String>, // 🔒 Encrypted
+    pub requisition_number: Option<String>,
+    pub requisition_document_id: Option<Uuid>,
+    pub ordered_at: DateTime<Utc>,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_by: Option<Uuid>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl LabOrder {
+    /// Decrypt into the API representation
+    pub fn decrypt(
+        &self,
+        key: &EncryptionKey,
+        tests: Vec<LabOrderTest>,
+    ) -> Result<LabOrderResponse> {
+        Ok(LabOrderResponse {
+            id: self.id,
+            patient_id: self.patient_id,
+            visit_id: self.visit_id,
+            visit_date: self.visit_date,
+            status: self.status.clone(),
+            urgency: self.urgency.clone(),
+            fasting: self.fasting,
+            lab_name: self.lab_name.clone(),
+            clinical_info: key
+                .decrypt_optional(&self.clinical_info)
+                .context("Failed to decrypt lab order clinical_info")?,
+            tests,
+            requisition_number: self.requisition_number.clone(),
+            requisition_document_id: self.requisition_document_id,
+            ordered_at: self.ordered_at,
+            created_by: self.created_by,
+            created_at: self.created_at,
+            updated_by: self.updated_by,
+            updated_at: self.updated_at,
+        })
+    }
+}
+
+/// Test requested by an order
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, Validate)]
+pub struct LabOrderTest {
+    #[serde(skip)]
+    pub order_id: Uuid,
+    #[validate(length(min = 3, max = 10, message = "LOINC code must be 3-10 characters"))]
+    pub loinc_code: String,
+    #[validate(length(min = 1, max = 255, message = "Test name must be 1-255 characters"))]
+    pub test_name: String,
+}
+
+/// Lab order (API output, decrypted)
+#[derive(Debug, Clone, Serialize)]
+pub struct LabOrderResponse {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub visit_id: Option<Uuid>,
+    pub visit_date: Option<NaiveDate>,
+    pub status: String,
+    pub urgency: String,
+    pub fasting: bool,
+    pub lab_name: Option<String>,
+    pub clinical_info: Option<String>,
+    /// In the order they were requested
+    pub tests: Vec<LabOrderTest>,
+    /// Assigned when the first requisition is generated
+    pub requisition_number: Option<String>,
+    /// Most recently generated requisition
+    pub requisition_document_id: Option<Uuid>,
+    pub ordered_at: DateTime<Utc>,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_by: Option<Uuid>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Request body for POST /api/v1/patients/:id/lab-orders
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct CreateLabOrderRequest {
+    /// Visit of the same patient the tests are ordered at
+    pub visit_id: Option<Uuid>,
+    #[serde(default)]
+    pub urgency: LabUrgency,
+    #[serde(default)]
+    pub fasting: bool,
+    #[validate(length(max = 255))]
+    pub lab_name: Option<String>,
+    #[validate(length(max = 5000, message = "Clinical information too long (max 5000 chars)"))]
+    pub clinical_info: Option<String>,
+    #[validate(
+        length(min = 1, max = 50, message = "An order must request 1-50 tests"),
+        nested
+    )]
+    pub tests: Vec<LabOrderTest>,
+}
+
+/// Request body for PUT /api/v1/patients/:id/lab-orders/:order_id
+///
+/// Absent fields are left unchanged. The requested tests cannot be changed;
+/// cancel the order and place a new one instead.
+#[derive(Debug, Clone, Default, Deserialize, Validate)]
+pub struct UpdateLabOrderRequest {
+    pub status: Option<LabOrderStatus>,
+    pub urgency: Option<LabUrgency>,
+    pub fasting: Option<bool>,
+    #[validate(length(max = 255))]
+    pub lab_name: Option<String>,
+    #[validate(length(max = 5000, message = "Clinical information too long (max 5000 chars)"))]
+    pub clinical_info: Option<String>,
+}
+
+/// Query parameters for GET /api/v1/patients/:id/lab-orders
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ListLabOrdersQuery {
+    pub status: Option<LabOrderStatus>,
+    pub visit_id: Option<Uuid>,
+}
+
+/// Orderable test in the lab catalog
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct LabCatalogTest {
+    pub loinc_code: String,
+    pub test_name: String,
+    pub category: String,
+    pub specimen: String,
+    pub fasting_required: bool,
+}
+
+/// Query parameters for GET /api/v1/lab-tests
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct LabCatalogQuery {
+    /// Matches the test name or LOINC code
+    pub search: Option<String>,
+    pub category: Option<String>,
+}
+
+/// Request body for POST /api/v1/patients/:id/lab-orders/:order_id/requisition
+#[derive(Debug, Clone, Default, Deserialize, Validate)]
+pub struct GenerateLabRequisitionRequest {
+    /// LAB_REQUEST template to use; defaults to the default one for `language`
+    pub template_id: Option<Uuid>,
+    #[serde(default)]
+    pub language: TemplateLanguage,
+    /// Printed as the diagnostic question; defaults to the order's clinical
+    /// information
+    #[validate(length(max = 1000, message = "Diagnostic question too long (max 1000 chars)"))]
+    pub diagnostic_question: Option<String>,
+    #[validate(length(max = 1000, message = "Special instructions too long (max 1000 chars)"))]
+    pub special_instructions: Option<String>,
+}
+
+/// Generated requisition and the order it was generated for (API output)
+#[derive(Debug, Clone, Serialize)]
+pub struct LabRequisitionResponse {
+    pub order: LabOrderResponse,
+    pub document: GeneratedDocumentResponse,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_lab_order_request_validation() {
+        let request: CreateLabOrderRequest = serde_json::from_str(
+            r#"{"tests": [{"loinc_code": "2345-7", "test_name": "Glucose"}], "fasting": true}"#,
+        )
+        .unwrap();
+        assert!(request.validate().is_ok());
+        assert_eq!(request.urgency, LabUrgency::Routine);
+        assert!(request.fasting);
+
+        let empty: CreateLabOrderRequest = serde_json::from_str(r#"{"tests": []}"#).unwrap();
+        assert!(empty.validate().is_err());
+
+        let unnamed: CreateLabOrderRequest =
+            serde_json::from_str(r#"{"tests": [{"loinc_code": "718-7", "test_name": ""}]}"#)
+                .unwrap();
+        assert!(unnamed.validate().is_err());
+    }
+
+    #[test]
+    fn test_generate_lab_requisition_request_defaults() {
+        let request: GenerateLabRequisitionRequest = serde_json::from_str("{}").unwrap();
+        assert!(request.validate().is_ok());
+        assert!(request.template_id.is_none());
+        assert_eq!(request.language, TemplateLanguage::Italian);
+
+        let too_long = GenerateLabRequisitionRequest {
+            diagnostic_question: Some("x".repeat(1001)),
+            ..Default::default()
+        };
+        assert!(too_long.validate().is_err());
+    }
+
+    #[test]
+    fn test_lab_order_test_serialization_skips_order_id() {
+        let test = LabOrderTest {
+            order_id: Uuid::new_v4(),
+            loinc_code: "718-7".to_string(),
+            test_name: "Hemoglobin".to_string(),
+        };
+        let json = serde_json::to_value(&test).unwrap();
+        assert!(json.get("order_id").is_none());
+        assert_eq!(json["loinc_code"], "718-7");
+    }
+}
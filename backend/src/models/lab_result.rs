@@ -0,0 +1,353 @@
+/*!
+ * Lab Result Model
+ *
+ * LOINC-coded laboratory results. The value is encrypted and kept as text,
+ * since not every analyte is numeric ("positive", "1:160"); numeric values
+ * are parsed on read for trends and the abnormal flag. Unit, reference range
+ * and flag stay in clear.
+ */
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::utils::encryption::EncryptionKey;
+
+/// Interpretation of a result against its reference range
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AbnormalFlag {
+    Normal,
+    Low,
+    High,
+    CriticalLow,
+    CriticalHigh,
+    /// Abnormal non-numeric result (e.g. a positive culture)
+    Abnormal,
+}
+
+impl AbnormalFlag {
+    /// Convert to database string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AbnormalFlag::Normal => "NORMAL",
+            AbnormalFlag::Low => "LOW",
+            AbnormalFlag::High => "HIGH",
+            AbnormalFlag::CriticalLow => "CRITICAL_LOW",
+            AbnormalFlag::CriticalHigh => "CRITICAL_HIGH",
+            AbnormalFlag::Abnormal => "ABNORMAL",
+        }
+    }
+
+    /// Flag a numeric value against a reference range
+    ///
+    /// Returns None without a range. Critical levels are not derived: they
+    /// depend on the analyte and are set by whoever records the result.
+    pub fn from_range(value: f64, low: Option<f64>, high: Option<f64>) -> Option<Self> {
+        if low.is_none() && high.is_none() {
+            return None;
+        }
+        if low.is_some_and(|low| value < low) {
+            Some(AbnormalFlag::Low)
+        } else if high.is_some_and(|high| value > high) {
+            Some(AbnormalFlag::High)
+        } else {
+            Some(AbnormalFlag::Normal)
+        }
+    }
+}
+
+/// Validate a LOINC code: 1-7 digits, a dash and the mod 10 check digit
+/// (e.g. 2345-7)
+pub fn validate_loinc_code(code: &str) -> Result<()> {
+    let Some((number, check)) = code.split_once('-') else {
+        anyhow::bail!("LOINC code must have the form NNNNN-N (e.g. 2345-7)");
+    };
+
+    if number.is_empty()
+        || number.len() > 7
+        || check.len() != 1
+        || !number
+            .chars()
+            .chain(check.chars())
+            .all(|c| c.is_ascii_digit())
+    {
+        anyhow::bail!("LOINC code must have the form NNNNN-N (e.g. 2345-7)");
+    }
+
+    // Luhn: double every other digit, starting from the rightmost
+    let sum: u32 = number
+        .chars()
+        .rev()
+        .filter_map(|c| c.to_digit(10))
+        .enumerate()
+        .map(|(i, digit)| {
+            if i % 2 == 0 {
+                let doubled = digit * 2;
+                doubled / 10 + doubled % 10
+            } else {
+                digit
+            }
+        })
+        .sum();
+    let expected = (10 - sum % 10) % 10;
+
+    if check.chars().next().and_then(|c| c.to_digit(10)) != Some(expected) {
+        anyhow::bail!("LOINC code {} has an invalid check digit", code);
+    }
+
+    Ok(())
+}
+
+/// Parse a result value as a number; accepts a decimal comma ("5,4")
+///
+/// Censored values ("<0.5") and text results are not numeric.
+pub fn parse_numeric_value(value: &str) -> Option<f64> {
+    value
+        .trim()
+        .replace(',', ".")
+        .parse::<f64>()
+        .ok()
+        .filter(|v| v.is_finite())
+}
+
+/// Lab result row (encrypted)
+#[derive(Debug, Clone, FromRow)]
+pub struct LabResult {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub order_id: Option<Uuid>,
+    pub visit_id: Option<Uuid>,
+    pub visit_date: Option<NaiveDate>,
+    pub loinc_code: String,
+    pub test_name: String,
+    pub value: String, // 🔒 Encrypted
+    pub unit: Option<String>,
+    pub reference_low: Option<f64>,
+    pub reference_high: Option<f64>,
+    pub reference_text: Option<String>,
+    pub abnormal_flag: Option<String>,
+    pub collected_at: DateTime<Utc>,
+    pub resulted_at: Option<DateTime<Utc>>,
+    pub notes: Option<String>, // 🔒 Encrypted
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_by: Option<Uuid>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl LabResult {
+    /// Decrypt into the API representation
+    pub fn decrypt(&self, key: &EncryptionKey) -> Result<LabResultResponse> {
+        let value = key
+            .decrypt(&self.value)
+            .context("Failed to decrypt lab result value")?;
+
+        Ok(LabResultResponse {
+            id: self.id,
+            patient_id: self.patient_id,
+            order_id: self.order_id,
+            visit_id: self.visit_id,
+            visit_date: self.visit_date,
+            loinc_code: self.loinc_code.clone(),
+            test_name: self.test_name.clone(),
+            numeric_value: parse_numeric_value(&value),
+            value,
+            unit: self.unit.clone(),
+            reference_low: self.reference_low,
+            reference_high: self.reference_high,
+            reference_text: self.reference_text.clone(),
+            abnormal_flag: self.abnormal_flag.clone(),
+            collected_at: self.collected_at,
+            resulted_at: self.resulted_at,
+            notes: key
+                .decrypt_optional(&self.notes)
+                .context("Failed to decrypt lab result notes")?,
+            created_by: self.created_by,
+            created_at: self.created_at,
+            updated_by: self.updated_by,
+            updated_at: self.updated_at,
+        })
+    }
+}
+
+/// Lab result (API output, decrypted)
+#[derive(Debug, Clone, Serialize)]
+pub struct LabResultResponse {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub order_id: Option<Uuid>,
+    pub visit_id: Option<Uuid>,
+    pub visit_date: Option<NaiveDate>,
+    pub loinc_code: String,
+    pub test_name: String,
+    pub value: String,
+    /// `value` as a number, when it is one
+    pub numeric_value: Option<f64>,
+    pub unit: Option<String>,
+    pub reference_low: Option<f64>,
+    pub reference_high: Option<f64>,
+    pub reference_text: Option<String>,
+    pub abnormal_flag: Option<String>,
+    pub collected_at: DateTime<Utc>,
+    pub resulted_at: Option<DateTime<Utc>>,
+    pub notes: Option<String>,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_by: Option<Uuid>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Request body for POST /api/v1/patients/:id/lab-results
+///
+/// Without an `abnormal_flag`, numeric values are flagged against the
+/// reference range.
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct CreateLabResultRequest {
+    /// Order of the same patient the result answers
+    pub order_id: Option<Uuid>,
+    /// Visit of the same patient; defaults to the order's visit
+    pub visit_id: Option<Uuid>,
+    #[validate(length(min = 3, max = 10, message = "LOINC code must be 3-10 characters"))]
+    pub loinc_code: String,
+    #[validate(length(min = 1, max = 255, message = "Test name must be 1-255 characters"))]
+    pub test_name: String,
+    #[validate(length(min = 1, max = 255, message = "Value must be 1-255 characters"))]
+    pub value: String,
+    #[validate(length(max = 50))]
+    pub unit: Option<String>,
+    pub reference_low: Option<f64>,
+    pub reference_high: Option<f64>,
+    #[validate(length(max = 100))]
+    pub reference_text: Option<String>,
+    pub abnormal_flag: Option<AbnormalFlag>,
+    pub collected_at: DateTime<Utc>,
+    pub resulted_at: Option<DateTime<Utc>>,
+    #[validate(length(max = 5000))]
+    pub notes: Option<String>,
+}
+
+/// Request body for PUT /api/v1/patients/:id/lab-results/:result_id
+///
+/// Absent fields are left unchanged. Changing the value or the range without
+/// an `abnormal_flag` flags the result again.
+#[derive(Debug, Clone, Default, Deserialize, Validate)]
+pub struct UpdateLabResultRequest {
+    #[validate(length(min = 1, max = 255, message = "Value must be 1-255 characters"))]
+    pub value: Option<String>,
+    #[validate(length(max = 50))]
+    pub unit: Option<String>,
+    pub reference_low: Option<f64>,
+    pub reference_high: Option<f64>,
+    #[validate(length(max = 100))]
+    pub reference_text: Option<String>,
+    pub abnormal_flag: Option<AbnormalFlag>,
+    pub collected_at: Option<DateTime<Utc>>,
+    pub resulted_at: Option<DateTime<Utc>>,
+    #[validate(length(max = 5000))]
+    pub notes: Option<String>,
+}
+
+/// Query parameters for GET /api/v1/patients/:id/lab-results
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ListLabResultsQuery {
+    pub loinc_code: Option<String>,
+    pub order_id: Option<Uuid>,
+    pub visit_id: Option<Uuid>,
+    /// Only results flagged other than NORMAL
+    #[serde(default)]
+    pub abnormal_only: bool,
+    /// Collected on or after this date
+    pub from: Option<NaiveDate>,
+    /// Collected on or before this date
+    pub to: Option<NaiveDate>,
+}
+
+/// Query parameters for GET /api/v1/patients/:id/lab-results/trend
+#[derive(Debug, Clone, Deserialize)]
+pub struct LabTrendQuery {
+    pub loinc_code: String,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+/// One analyte of a patient over time (API output)
+#[derive(Debug, Clone, Serialize)]
+pub struct LabTrend {
+    pub loinc_code: String,
+    /// Name of the most recent result
+    pub test_name: Option<String>,
+    /// Oldest first
+    pub points: Vec<LabTrendPoint>,
+}
+
+/// Result in a trend
+#[derive(Debug, Clone, Serialize)]
+pub struct LabTrendPoint {
+    pub result_id: Uuid,
+    pub collected_at: DateTime<Utc>,
+    pub value: String,
+    pub numeric_value: Option<f64>,
+    pub unit: Option<String>,
+    pub reference_low: Option<f64>,
+    pub reference_high: Option<f64>,
+    pub abnormal_flag: Option<String>,
+}
+
+impl From<LabResultResponse> for LabTrendPoint {
+    fn from(result: LabResultResponse) -> Self {
+        Self {
+            result_id: result.id,
+            collected_at: result.collected_at,
+            value: result.value,
+            numeric_value: result.numeric_value,
+            unit: result.unit,
+            reference_low: result.reference_low,
+            reference_high: result.reference_high,
+            abnormal_flag: result.abnormal_flag,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_loinc_code() {
+        assert!(validate_loinc_code("2345-7").is_ok()); // Glucose
+        assert!(validate_loinc_code("718-7").is_ok()); // Hemoglobin
+        assert!(validate_loinc_code("4548-4").is_ok()); // HbA1c
+
+        assert!(validate_loinc_code("2345-8").is_err()); // Wrong check digit
+        assert!(validate_loinc_code("2345").is_err());
+        assert!(validate_loinc_code("-7").is_err());
+        assert!(validate_loinc_code("23A5-7").is_err());
+        assert!(validate_loinc_code("12345678-9").is_err());
+    }
+
+    #[test]
+    fn test_abnormal_flag_and_numeric_value() {
+        assert_eq!(parse_numeric_value(" 5,4 "), Some(5.4));
+        assert_eq!(parse_numeric_value("126"), Some(126.0));
+        assert_eq!(parse_numeric_value("<0.5"), None);
+        assert_eq!(parse_numeric_value("positive"), None);
+
+        assert_eq!(
+            AbnormalFlag::from_range(126.0, Some(70.0), Some(99.0)),
+            Some(AbnormalFlag::High)
+        );
+        assert_eq!(
+            AbnormalFlag::from_range(65.0, Some(70.0), None),
+            Some(AbnormalFlag::Low)
+        );
+        assert_eq!(
+            AbnormalFlag::from_range(99.0, Some(70.0), Some(99.0)),
+            Some(AbnormalFlag::Normal)
+        );
+        assert_eq!(AbnormalFlag::from_range(5.0, None, None), None);
+    }
+}
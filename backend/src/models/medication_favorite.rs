@@ -0,0 +1,205 @@
+/*!
+ * Medication Favorite Models
+ *
+ * Per-prescriber favorite medications with default dosing, and how often
+ * the prescriber has prescribed each medication. Favorites come first in
+ * medication search and in the quick-pick list; prescribing history ranks
+ * the rest. No patient data, so nothing here is encrypted.
+ */
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+use super::prescription::{MedicationForm, RouteOfAdministration};
+
+/// Most favorites a prescriber can keep
+pub const MAX_MEDICATION_FAVORITES: i64 = 100;
+
+/// Favorite medication row (also the API output)
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct MedicationFavorite {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub medication_name: String,
+    pub generic_name: Option<String>,
+    pub form: Option<String>,
+    pub route: Option<String>,
+    pub dosage: Option<String>,
+    pub frequency: Option<String>,
+    pub duration: Option<String>,
+    pub quantity: Option<i32>,
+    pub refills: Option<i32>,
+    pub instructions: Option<String>,
+    pub sort_order: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl MedicationFavorite {
+    /// Default dosing to prefill the prescription form with
+    pub fn defaults(&self) -> MedicationDefaults {
+        MedicationDefaults {
+            dosage: self.dosage.clone(),
+            route: self.route.clone(),
+            frequency: self.frequency.clone(),
+            duration: self.duration.clone(),
+            quantity: self.quantity,
+            refills: self.refills,
+            instructions: self.instructions.clone(),
+        }
+    }
+}
+
+/// Default dosing of a favorite medication
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MedicationDefaults {
+    pub dosage: Option<String>,
+    pub route: Option<String>,
+    pub frequency: Option<String>,
+    pub duration: Option<String>,
+    pub quantity: Option<i32>,
+    pub refills: Option<i32>,
+    pub instructions: Option<String>,
+}
+
+/// Request body for POST /api/v1/prescriptions/medications/favorites
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct CreateMedicationFavoriteRequest {
+    #[validate(length(
+        min = 1,
+        max = 255,
+        message = "Medication name must be 1-255 characters"
+    ))]
+    pub medication_name: String,
+
+    #[validate(length(max = 255, message = "Generic name too long (max 255 chars)"))]
+    pub generic_name: Option<String>,
+
+    pub form: Option<MedicationForm>,
+    pub route: Option<RouteOfAdministration>,
+
+    #[validate(length(max = 100, message = "Dosage too long (max 100 chars)"))]
+    pub dosage: Option<String>,
+
+    #[validate(length(max = 100, message = "Frequency too long (max 100 chars)"))]
+    pub frequency: Option<String>,
+
+    #[validate(length(max = 100, message = "Duration too long (max 100 chars)"))]
+    pub duration: Option<String>,
+
+    #[validate(range(min = 1, max = 1000, message = "Quantity must be 1-1000"))]
+    pub quantity: Option<i32>,
+
+    #[validate(range(min = 0, max = 12, message = "Refills must be 0-12"))]
+    pub refills: Option<i32>,
+
+    #[validate(length(max = 500, message = "Instructions too long (max 500 chars)"))]
+    pub instructions: Option<String>,
+
+    /// Position in the quick-pick list; defaults to last
+    pub sort_order: Option<i32>,
+}
+
+/// Request body for PUT /api/v1/prescriptions/medications/favorites/:id
+///
+/// Only the given fields change.
+#[derive(Debug, Clone, Default, Deserialize, Validate)]
+pub struct UpdateMedicationFavoriteRequest {
+    #[validate(length(max = 255, message = "Generic name too long (max 255 chars)"))]
+    pub generic_name: Option<String>,
+
+    pub form: Option<MedicationForm>,
+    pub route: Option<RouteOfAdministration>,
+
+    #[validate(length(max = 100, message = "Dosage too long (max 100 chars)"))]
+    pub dosage: Option<String>,
+
+    #[validate(length(max = 100, message = "Frequency too long (max 100 chars)"))]
+    pub frequency: Option<String>,
+
+    #[validate(length(max = 100, message = "Duration too long (max 100 chars)"))]
+    pub duration: Option<String>,
+
+    #[validate(range(min = 1, max = 1000, message = "Quantity must be 1-1000"))]
+    pub quantity: Option<i32>,
+
+    #[validate(range(min = 0, max = 12, message = "Refills must be 0-12"))]
+    pub refills: Option<i32>,
+
+    #[validate(length(max = 500, message = "Instructions too long (max 500 chars)"))]
+    pub instructions: Option<String>,
+
+    pub sort_order: Option<i32>,
+}
+
+/// Medication the prescriber writes often (API output)
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct FrequentMedication {
+    pub medication_name: String,
+    pub prescription_count: i32,
+    pub last_prescribed_at: DateTime<Utc>,
+}
+
+/// Query parameters for GET /api/v1/prescriptions/medications/quick-pick
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct QuickPickQuery {
+    /// Most frequent medications to list (default 10, max 50)
+    pub limit: Option<i64>,
+}
+
+/// Quick-pick list: favorites, then the most prescribed medications that
+/// are not favorites (API output)
+#[derive(Debug, Clone, Serialize)]
+pub struct QuickPickResponse {
+    pub favorites: Vec<MedicationFavorite>,
+    pub frequent: Vec<FrequentMedication>,
+}
+
+/// Key medications are counted and matched under: trimmed, lowercased, single
+/// spaces
+pub fn medication_key(name: &str) -> String {
+    name.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_medication_key() {
+        assert_eq!(
+            medication_key("  Tachipirina   500 MG "),
+            "tachipirina 500 mg"
+        );
+        assert_eq!(medication_key("Ibuprofen"), medication_key("ibuprofen"));
+    }
+
+    #[test]
+    fn test_create_medication_favorite_request_validation() {
+        let request: CreateMedicationFavoriteRequest = serde_json::from_value(serde_json::json!({
+            "medication_name": "Amoxicillina",
+            "form": "CAPSULE",
+            "dosage": "1 g",
+            "frequency": "2 volte al giorno",
+            "quantity": 12,
+        }))
+        .unwrap();
+        assert!(request.validate().is_ok());
+        assert_eq!(request.form, Some(MedicationForm::Capsule));
+
+        let request = CreateMedicationFavoriteRequest {
+            medication_name: String::new(),
+            refills: Some(20),
+            ..request
+        };
+        let errors = request.validate().unwrap_err();
+        assert!(errors.field_errors().contains_key("medication_name"));
+        assert!(errors.field_errors().contains_key("refills"));
+    }
+}
@@ -0,0 +1,146 @@
+/*!
+ * Medication Sync Model
+ *
+ * Runs of the AIFA medication import and the diff they report.
+ */
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// Outcome of a sync run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum MedicationSyncStatus {
+    Completed,
+    Failed,
+}
+
+impl MedicationSyncStatus {
+    /// Convert to database string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MedicationSyncStatus::Completed => "COMPLETED",
+            MedicationSyncStatus::Failed => "FAILED",
+        }
+    }
+}
+
+/// AIFA package of a medication (stored in `medications.packages`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MedicationPackage {
+    pub aic_code: String,
+    pub description: String,
+    /// Public price in EUR
+    pub price: Option<f64>,
+    pub equivalence_group: Option<String>,
+}
+
+/// Medication added to or withdrawn from the list
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MedicationDiffEntry {
+    pub name: String,
+    pub generic_name: Option<String>,
+    pub aic_code: Option<String>,
+}
+
+/// One changed field of a medication
+///
+/// Package changes carry the AIC code of the package (`package_added`,
+/// `package_removed`, `price`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MedicationFieldChange {
+    pub field: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aic_code: Option<String>,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+/// Medication whose AIFA data changed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MedicationChange {
+    pub name: String,
+    pub changes: Vec<MedicationFieldChange>,
+}
+
+/// Diff reported by a sync run
+///
+/// Lists are capped; the run counts are always complete.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MedicationSyncDiff {
+    pub added: Vec<MedicationDiffEntry>,
+    pub updated: Vec<MedicationChange>,
+    pub deactivated: Vec<MedicationDiffEntry>,
+    /// Some lists were cut at the cap
+    #[serde(default)]
+    pub truncated: bool,
+}
+
+/// Sync run row
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct MedicationSyncRun {
+    pub id: Uuid,
+    pub source: String,
+    pub dry_run: bool,
+    pub status: String,
+    pub records_parsed: i32,
+    pub atc_codes_imported: i32,
+    pub added_count: i32,
+    pub updated_count: i32,
+    pub unchanged_count: i32,
+    pub deactivated_count: i32,
+    pub diff: serde_json::Value,
+    pub error_message: Option<String>,
+    pub triggered_by: Option<Uuid>,
+    pub started_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+/// Request body for POST /api/v1/medication-sync/run
+#[derive(Debug, Clone, Deserialize)]
+pub struct RunMedicationSyncRequest {
+    /// Only report what would change (default true)
+    #[serde(default = "default_true")]
+    pub dry_run: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Query parameters for GET /api/v1/medication-sync/runs
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ListMedicationSyncRunsQuery {
+    /// Maximum runs returned (default 20, max 200)
+    pub limit: Option<i64>,
+}
+
+impl ListMedicationSyncRunsQuery {
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(20).clamp(1, 200)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_request_defaults_to_dry_run() {
+        let request: RunMedicationSyncRequest = serde_json::from_str("{}").unwrap();
+        assert!(request.dry_run);
+
+        let request: RunMedicationSyncRequest =
+            serde_json::from_str(r#"{"dry_run": false}"#).unwrap();
+        assert!(!request.dry_run);
+    }
+
+    #[test]
+    fn test_list_runs_limit_is_clamped() {
+        assert_eq!(ListMedicationSyncRunsQuery::default().limit(), 20);
+        let query = ListMedicationSyncRunsQuery { limit: Some(5000) };
+        assert_eq!(query.limit(), 200);
+    }
+}
@@ -4,40 +4,211 @@
  * Contains database models and their associated operations.
  */
 
+pub mod access_delegation;
 pub mod appointment;
+pub mod appointment_link;
 pub mod audit_log;
+pub mod authorization_policy;
+pub mod batch;
+pub mod booking;
+pub mod branding;
+pub mod calendar_feed;
 pub mod request_context;
 pub mod document_template;
+pub mod domain_event;
+pub mod e_prescription;
 pub mod generated_document;
 pub mod holiday;
+pub mod imaging_order;
+pub mod impersonation;
+pub mod lab_order;
+pub mod lab_result;
 pub mod notification;
 pub mod patient;
+pub mod patient_allergy;
+pub mod patient_attachment;
+pub mod patient_chart;
+pub mod patient_cohort;
+pub mod patient_communication;
+pub mod patient_consent;
+pub mod patient_erasure;
+pub mod patient_export;
+pub mod patient_merge;
+pub mod patient_message;
+pub mod patient_photo;
+pub mod patient_portal;
+pub mod patient_problem;
+pub mod overbooking;
+pub mod preventive_reminder;
+pub mod system_alert;
 pub mod system_health;
+pub mod telemetry;
+pub mod televisit;
+pub mod trusted_device;
+pub mod recall;
 pub mod report;
+pub mod report_subscription;
+pub mod research_export;
+pub mod resource;
+pub mod rate_limit_quota;
+pub mod retention;
+pub mod trash;
 pub mod patient_insurance;
 pub mod prescription;
+pub mod prescription_print_batch;
+pub mod prescription_refill;
 pub mod prescription_template;
+pub mod referral;
 pub mod system_setting;
 pub mod uploaded_file;
 pub mod user;
+pub mod user_invitation;
 pub mod visit;
+pub mod visit_addendum;
+pub mod visit_attachment;
+pub mod visit_calculation;
+pub mod visit_cosign;
+pub mod medication_favorite;
+pub mod medication_sync;
 pub mod working_hours;
 pub mod visit_diagnosis;
+pub mod visit_search;
+pub mod visit_survey;
 pub mod visit_template;
 pub mod visit_version;
+pub mod vitals_trend;
+pub mod waiting_room;
+pub mod waitlist;
 
 pub use appointment::{
-    Appointment, AppointmentDto, AppointmentSearchFilter, AppointmentStatistics,
-    AppointmentStatus, AppointmentType, AvailabilityResponse,
-    CancelAppointmentRequest, CreateAppointmentRequest, RecurringFrequency, RecurringPattern,
-    TimeSlot, UpdateAppointmentRequest,
+    earliest_slots, parse_appointment_buffers, AlternativeSlot, Appointment, AppointmentBuffer,
+    AppointmentBuffers, AppointmentDto, AppointmentSearchFilter, AppointmentStatistics,
+    AppointmentStatus, AppointmentType, AvailabilityResponse, CancelAppointmentRequest,
+    CreateAppointmentRequest, MultiProviderAvailabilityResponse, ProviderAvailability,
+    ProviderSlot, RecurringFrequency, RecurringPattern, SchedulingConflict,
+    SchedulingConflictKind, TimeSlot, UpdateAppointmentRequest, APPOINTMENT_BUFFERS_SETTING,
+    MAX_BUFFER_MINUTES,
+};
+pub use appointment_link::{
+    AppointmentLinkAction, AppointmentLinkClaims, AppointmentLinkOutcome, AppointmentLinkRequest,
+    AppointmentLinkResponse,
 };
 pub use audit_log::{AuditAction, AuditLog, CreateAuditLog, EntityType};
+pub use batch::{BatchOperation, BatchOperationResult, BatchRequest, BatchResponse};
+pub use booking::{
+    BookingConfirmationResponse, BookingOptionsResponse, BookingRequestAccepted,
+    BookingSlotsQuery, BookingSlotsResponse, CreateBookingRequest, VerifyBookingRequest,
+};
+pub use calendar_feed::{
+    calendar_feed_path, generate_calendar_feed_token, hash_calendar_feed_token, render_calendar,
+    CalendarEvent, CalendarFeed, CalendarFeedRequest, CalendarPrivacyMode, CreatedCalendarFeed,
+    FEED_FUTURE_DAYS, FEED_PAST_DAYS,
+};
 pub use request_context::RequestContext;
+pub use imaging_order::{
+    CreateImagingOrderRequest, GenerateImagingReferralRequest, ImagingLaterality, ImagingModality,
+    ImagingOrder, ImagingOrderResponse, ImagingOrderStatus, ImagingReferralResponse,
+    ImagingUrgency, ListImagingOrdersQuery, UpdateImagingOrderRequest,
+};
+pub use lab_order::{
+    CreateLabOrderRequest, GenerateLabRequisitionRequest, LabCatalogQuery, LabCatalogTest,
+    LabOrder, LabOrderResponse, LabOrderStatus, LabOrderTest, LabRequisitionResponse, LabUrgency,
+    ListLabOrdersQuery, UpdateLabOrderRequest,
+};
+pub use lab_result::{
+    AbnormalFlag, CreateLabResultRequest, LabResult, LabResultResponse, LabTrend, LabTrendPoint,
+    LabTrendQuery, ListLabResultsQuery, UpdateLabResultRequest,
+};
 pub use patient::{
-    CreatePatientRequest, Patient,
+    CreatePatientRequest, Patient, PatientAutocompleteQuery, PatientBlindIndexes,
     PatientDto, PatientSearchFilter, UpdatePatientRequest,
 };
+pub use patient_allergy::{
+    AllergyCategory, AllergySeverity, AllergyStatus, AllergySummary, AllergyType,
+    CreatePatientAllergyRequest, ListPatientAllergiesQuery, PatientAllergy, PatientAllergyResponse,
+    UpdatePatientAllergyRequest,
+};
+pub use patient_attachment::{
+    AttachmentCategory, CreatePatientAttachmentRequest, ListPatientAttachmentsQuery, OcrStatus,
+    OcrSuggestions, PatientAttachment, PatientAttachmentResponse, UpdatePatientAttachmentRequest,
+};
+pub use patient_cohort::{
+    CampaignType, CohortCampaignSkip, CohortCriteria, CohortDocumentsRequest, CohortMember,
+    CohortNotificationRequest, CohortNotificationResult, CohortPreview, CreatePatientCohortRequest,
+    PatientCohort, PatientCohortResponse, UpdatePatientCohortRequest, COHORT_PREVIEW_LIMIT,
+    MAX_CAMPAIGN_RECIPIENTS,
+};
+pub use patient_communication::{
+    CommunicationAnnotation, CommunicationAnnotationResponse, CommunicationChannel,
+    CommunicationDirection, CommunicationSource, CreateCommunicationAnnotationRequest,
+    CreatePatientCommunicationRequest, ListPatientCommunicationsQuery,
+    ListPatientCommunicationsResponse, PatientCommunication, PatientCommunicationResponse,
+};
+pub use patient_consent::{
+    ConsentStatus, ConsentText, ConsentTextResponse, ConsentType, CreateConsentTextRequest,
+    ListConsentTextsQuery, ListPatientConsentsQuery, PatientConsent, PatientConsentDetailResponse,
+    PatientConsentResponse, RecordConsentRequest, SignatureMethod, WithdrawConsentRequest,
+};
+pub use patient_erasure::{
+    ApproveErasureRequest, CreateErasureRequest, ErasureRequestStatus, ErasureSummary,
+    ListErasureRequestsQuery, PatientErasureRequest, PatientErasureRequestResponse,
+    RejectErasureRequest,
+};
+pub use patient_message::{
+    CreateMessageThreadRequest, CreatePatientMessageRequest, MarkMessagesReadResponse,
+    MessageAttachmentResponse, MessageInboxQuery, MessageInboxResponse, MessageSender, MessageThreadDetailResponse,
+    MessageThreadResponse, MessageThreadStatus, MessageUnreadCountResponse, PatientMessage,
+    PatientMessageAttachment, PatientMessageResponse, PatientMessageThread,
+    UpdateMessageThreadRequest, MAX_MESSAGE_ATTACHMENTS,
+};
+pub use patient_export::{
+    CreatePatientExportRequest, PatientExport, PatientExportResponse, PatientExportStatus,
+};
+pub use patient_chart::{
+    PatientChartQuery, PatientChartResponse, PatientChartSections, DEFAULT_CHART_SECTION_LIMIT,
+};
+pub use patient_merge::{MergePatientsRequest, PatientMergeResponse, PatientMergeSummary};
+pub use patient_photo::PatientPhotoResponse;
+pub use patient_portal::{
+    PortalAppointmentResponse, PortalDocumentResponse, PortalLoginLinkRequest,
+    PortalLoginLinkResponse, PortalPatient, PortalProfileResponse, PortalSessionRequest,
+    PortalSessionResponse, UpdatePortalContactRequest,
+};
+pub use patient_problem::{
+    CreatePatientProblemRequest, LinkProblemVisitRequest, LinkedVisit, ListPatientProblemsQuery,
+    PatientProblem, PatientProblemResponse, ProblemStatus, UpdatePatientProblemRequest,
+};
+pub use preventive_reminder::{
+    is_birthday, parse_screening_programs, ScreeningProgram, MAX_SCREENING_INTERVAL_MONTHS,
+    MAX_SCREENING_PROGRAMS, SCREENING_PROGRAMS_SETTING,
+};
+pub use recall::{
+    CreateRecallOptOutRequest, CreateRecallRuleRequest, RecallCriteria, RecallEntry,
+    RecallEntryFilter, RecallEntryResponse, RecallEntryStatus, RecallOptOut, RecallOptOutFilter,
+    RecallOutcome, RecallRule, RecallRuleResponse, RecallRun, UpdateRecallRuleRequest,
+};
+pub use referral::{
+    CreateReferralRequest, GenerateReferralLetterRequest, ListReferralsQuery, OverdueReferral,
+    OverdueReferralsQuery, Referral, ReferralLetterResponse, ReferralResponse, ReferralStatus,
+    ReferralUrgency, UpdateReferralRequest,
+};
+pub use report_subscription::{
+    CreateReportSubscriptionRequest, ReportCadence, ReportDeliveryStatus, ReportSubscription,
+    ReportSubscriptionFilters, ReportSubscriptionResponse, UpdateReportSubscriptionRequest,
+};
+pub use research_export::{
+    AnonymizationProfile, AnonymizationSummary, AnonymizedDataset, DatePrecision, Icd10Precision,
+    ResearchDatasetType, ResearchExportRequest, ResearchRecord, DEFAULT_RESEARCH_EXPORT_MIN_K,
+};
+pub use rate_limit_quota::{
+    RateLimitDefaults, RateLimitOverview, RateLimitQuota, SetRateLimitQuotaRequest,
+    UserRateLimitStatus,
+};
+pub use retention::{
+    ListRetentionRunsQuery, RetentionEntityType, RetentionRule, RetentionRuleResponse,
+    RetentionRun, RetentionRunStatus, RunRetentionRequest, UpdateRetentionRuleRequest,
+};
+pub use trash::{ListTrashQuery, TrashEntityType, TrashItem, TrashListResponse};
 pub use user::{User, UserDto, UserRole};
 
 /// Authenticated user information extracted from JWT token
@@ -51,44 +222,116 @@ pub use visit::{
     CreateVisitRequest, UpdateVisitRequest, Visit, VisitResponse, VisitStatus,
     VisitType,
 };
+pub use visit_addendum::{
+    AddendumType, CreateVisitAddendumRequest, VisitAddendum, VisitAddendumResponse,
+};
+pub use visit_attachment::{
+    CreateVisitAttachmentRequest, TranscriptProposal, TranscriptionStatus, VisitAttachment,
+    VisitAttachmentKind, VisitAttachmentResponse,
+};
+pub use visit_calculation::{VisitCalculation, VisitCalculationResponse};
+pub use overbooking::{OverbookingPolicy, SetOverbookingPolicyRequest, MAX_PARALLEL_BOOKINGS};
+pub use resource::{
+    mark_busy_resources, CreateResourceRequest, Resource, ResourceBooking, ResourceQuery,
+    ResourceType, UpdateResourceRequest,
+};
+pub use visit_cosign::{
+    CosignQueueItem, CosignQueueQuery, CosignQueueRow, CosignRequirement, ReturnVisitRequest,
+    SetCosignRequirementRequest,
+};
+pub use medication_favorite::{
+    CreateMedicationFavoriteRequest, FrequentMedication, MedicationDefaults, MedicationFavorite,
+    QuickPickQuery, QuickPickResponse, UpdateMedicationFavoriteRequest,
+};
+pub use medication_sync::{
+    ListMedicationSyncRunsQuery, MedicationChange, MedicationDiffEntry, MedicationFieldChange,
+    MedicationPackage, MedicationSyncDiff, MedicationSyncRun, MedicationSyncStatus,
+    RunMedicationSyncRequest,
+};
+pub use vitals_trend::{
+    GrowthPercentiles, VitalsTrendPoint, VitalsTrendQuery, VitalsTrendResponse,
+};
 pub use visit_diagnosis::{
     CreateVisitDiagnosisRequest, DiagnosisType, UpdateVisitDiagnosisRequest,
     VisitDiagnosis, VisitDiagnosisResponse,
 };
+pub use e_prescription::{
+    EPrescriptionCallback, EPrescriptionEvent, EPrescriptionEventSource, EPrescriptionResponse,
+    EPrescriptionStatus, GenerateNrePromemoriaRequest, NrePromemoria, NrePromemoriaResponse,
+    SubmitEPrescriptionRequest,
+};
 pub use prescription::{
-    CreatePrescriptionRequest, DrugInteractionWarning, MedicationForm,
-    Prescription, PrescriptionResponse, PrescriptionStatus,
+    AllergyMatchType, CreatePrescriptionRequest, DrugAllergyAlert, DrugInteractionWarning,
+    GenerateSsnPrescriptionRequest, MedicationForm,
+    Prescription, PrescriptionResponse, PrescriptionStatus, SsnPriority, SSN_MAX_PACKAGES,
     UpdatePrescriptionRequest,
 };
+pub use prescription_print_batch::{
+    CreatePrescriptionPrintBatchRequest, PrescriptionPrintBatch, PrescriptionPrintBatchResponse,
+    PrescriptionPrintLayout, PrintBatchStatus,
+};
+pub use prescription_refill::{
+    OverdueRefill, OverdueRefillsQuery, RefillReminderSkipReason, RefillRemindersResponse,
+    SendRefillRemindersRequest, SkippedRefillReminder,
+};
 pub use prescription_template::{
     CreatePrescriptionTemplateRequest, PrescriptionTemplate, PrescriptionTemplateResponse,
     TemplateMedication, UpdatePrescriptionTemplateRequest,
 };
+pub use visit_search::{
+    VisitSearchHighlight, VisitSearchHit, VisitSearchQuery, VisitSearchResponse,
+};
+pub use visit_survey::{
+    net_promoter_score, VisitSurveyAnswerRequest, VisitSurveyAnswerResponse, VisitSurveyClaims,
+};
 pub use visit_template::{
     CreateVisitTemplateRequest, UpdateVisitTemplateRequest, VisitTemplate,
     VisitTemplateResponse,
 };
 pub use visit_version::{VisitVersionResponse, VisitVersionSummary};
+pub use waiting_room::{
+    validate_arrival_time, waiting_minutes, CheckInRequest, WaitingRoomEntry, WaitingRoomQuery,
+    WaitingRoomQueue, WaitingRoomRow,
+};
+pub use waitlist::{
+    waitlist_slot_offerable, CreateWaitlistEntryRequest, WaitlistEntry, WaitlistEntryStatus,
+    WaitlistOffer, WaitlistOfferAction, WaitlistOfferClaims, WaitlistOfferOutcome,
+    WaitlistOfferRequest, WaitlistOfferResponse, WaitlistOfferStatus, WaitlistQuery,
+    WaitlistSettings, MAX_WAITLIST_OFFERS_PER_ROUND, WAITLIST_OFFERS_PER_ROUND_SETTING_KEY,
+    WAITLIST_OFFER_EXPIRY_SETTING_KEY,
+};
 pub use document_template::{
     CreateDocumentTemplateRequest, DocumentTemplate, DocumentTemplateFilter,
     DocumentTemplateResponse, DocumentTemplateSummary, DocumentType, ListDocumentTemplatesResponse,
     PageOrientation, PageSize, TemplateLanguage, UpdateDocumentTemplateRequest,
 };
 pub use generated_document::{
-    DeliverDocumentRequest,
+    BulkGenerateError, BulkGenerateRequest, BulkGenerateResult, ChartSection, CreatePrintBundleRequest, DeliverDocumentRequest,
     DocumentStatistics, DocumentStatus, DocumentStatusCount, DocumentTypeCount,
     GenerateDocumentRequest, GeneratedDocument, GeneratedDocumentFilter,
-    GeneratedDocumentResponse, GeneratedDocumentSummary, ListGeneratedDocumentsResponse,
+    GeneratedDocumentResponse, GeneratedDocumentSummary, GenerationRequest,
+    ListGeneratedDocumentsResponse,
 };
 pub use report::{
-    AgeGroupCount, AppointmentReportFilter, AppointmentUtilizationReport, DailyAppointmentCount,
-    DashboardReport, DayOfWeekCount, DiagnosisCategoryCount, DiagnosisCount,
-    DiagnosisReportFilter, DiagnosisTrendsReport, ExportFormat, ExportReportRequest,
-    GenderBreakdown, HourlyCount, MonthlyCount, MonthlyDiagnosisCount, NewPatientSummary,
-    PatientReportFilter, PatientStatisticsReport, ProductivityReportFilter, ProductivitySummary,
-    ProviderProductivity, ProviderProductivityReport, QuickStats, RecentActivity,
+    AgeGroupCount, AppointmentHeatmapReport, AppointmentReportFilter,
+    AppointmentUtilizationReport, ComparisonPeriod, DailyAppointmentCount, DashboardReport,
+    DayOfWeekCount, DiagnosisCategoryCount, DiagnosisCount, DiagnosisReportFilter, DiagnosisTrendsReport,
+    ExportFormat, ExportReportRequest, GenderBreakdown, HeatmapSlotCount, HourlyCount, MetricChange, MonthlyCount, MonthlyDiagnosisCount, NewPatientSummary,
+    PatientReportFilter, PatientStatisticsReport, PeriodComparison, ProductivityReportFilter, ProductivitySummary,
+    ProviderProductivity, ProviderProductivityReport, QualityIndicator, QualityIndicatorFilter,
+    QualityIndicatorReport, QuickStats, RecentActivity,
     RecentAppointment, RecentVisit, ReportDateRange, ReportType, RevenueReport,
-    RevenueReportFilter,
+    RevenueReportFilter, SatisfactionSummary,
+};
+pub use system_alert::{AlertSeverity, CreateSystemAlert, ListSystemAlertsQuery, SystemAlert};
+pub use televisit::{
+    televisit_join_window, televisit_joinable, TelevisitAttendance, TelevisitClaims,
+    TelevisitJoinResponse, TelevisitLeaveResponse, TelevisitParticipant, TelevisitSession,
+    TelevisitTokenRequest, TELEVISIT_JOIN_EARLY_MINUTES, TELEVISIT_JOIN_GRACE_MINUTES,
+};
+pub use telemetry::{
+    ListTelemetryReportsQuery, TelemetryErrorRate, TelemetryErrors, TelemetryPayload,
+    TelemetryPreviewResponse, TelemetryReport, TelemetryUsage, TELEMETRY_SCHEMA_VERSION,
 };
 pub use system_health::{
     ApplicationInfo, BackupInfo, BackupStatusFile, BackupStatusResponse, ComponentHealth,
@@ -98,6 +341,6 @@ pub use system_health::{
 };
 pub use notification::{
     CreateNotificationRequest, ListNotificationsResponse, Notification,
-    NotificationFilter, NotificationResponse, NotificationStatistics, PatientNotificationPreferences, PatientNotificationPreferencesResponse,
+    NotificationFilter, NotificationResponse, NotificationStatistics, NotificationType, PatientNotificationPreferences, PatientNotificationPreferencesResponse,
     SendTestEmailRequest, SendTestEmailResponse, UpdateNotificationPreferencesRequest,
 };
@@ -29,6 +29,15 @@ pub enum NotificationType {
     PrescriptionReady,
     FollowUpReminder,
     Custom,
+    Marketing,                // Requires an active MARKETING consent
+    ReferralReminder,         // To the referring doctor, for an overdue specialist response
+    RefillReminder,           // To the patient, for a prescription past its expected refill date
+    BirthdayGreeting,         // To the patient, on their birthday
+    ScreeningReminder,        // To the patient, when due for a preventive screening
+    PatientCancellation,      // To the provider, when a patient cancels from a reminder link
+    VisitSurvey,              // To the patient, after a completed appointment
+    TelevisitInvitation,      // To the patient, with the join link of a televisit
+    WaitlistOffer,            // To a waitlisted patient, offering a freed slot
 }
 
 impl NotificationType {
@@ -43,6 +52,15 @@ impl NotificationType {
             Self::PrescriptionReady => "PRESCRIPTION_READY",
             Self::FollowUpReminder => "FOLLOW_UP_REMINDER",
             Self::Custom => "CUSTOM",
+            Self::Marketing => "MARKETING",
+            Self::ReferralReminder => "REFERRAL_REMINDER",
+            Self::RefillReminder => "REFILL_REMINDER",
+            Self::BirthdayGreeting => "BIRTHDAY_GREETING",
+            Self::ScreeningReminder => "SCREENING_REMINDER",
+            Self::PatientCancellation => "PATIENT_CANCELLATION",
+            Self::VisitSurvey => "VISIT_SURVEY",
+            Self::TelevisitInvitation => "TELEVISIT_INVITATION",
+            Self::WaitlistOffer => "WAITLIST_OFFER",
         }
     }
 
@@ -57,6 +75,15 @@ impl NotificationType {
             "PRESCRIPTION_READY" => Some(Self::PrescriptionReady),
             "FOLLOW_UP_REMINDER" => Some(Self::FollowUpReminder),
             "CUSTOM" => Some(Self::Custom),
+            "MARKETING" => Some(Self::Marketing),
+            "REFERRAL_REMINDER" => Some(Self::ReferralReminder),
+            "REFILL_REMINDER" => Some(Self::RefillReminder),
+            "BIRTHDAY_GREETING" => Some(Self::BirthdayGreeting),
+            "SCREENING_REMINDER" => Some(Self::ScreeningReminder),
+            "PATIENT_CANCELLATION" => Some(Self::PatientCancellation),
+            "VISIT_SURVEY" => Some(Self::VisitSurvey),
+            "TELEVISIT_INVITATION" => Some(Self::TelevisitInvitation),
+            "WAITLIST_OFFER" => Some(Self::WaitlistOffer),
             _ => None,
         }
     }
@@ -72,6 +99,15 @@ impl NotificationType {
             "PRESCRIPTION_READY",
             "FOLLOW_UP_REMINDER",
             "CUSTOM",
+            "MARKETING",
+            "REFERRAL_REMINDER",
+            "REFILL_REMINDER",
+            "BIRTHDAY_GREETING",
+            "SCREENING_REMINDER",
+            "PATIENT_CANCELLATION",
+            "VISIT_SURVEY",
+            "TELEVISIT_INVITATION",
+            "WAITLIST_OFFER",
         ]
     }
 }
@@ -0,0 +1,164 @@
+/*!
+ * Overbooking Policy Models
+ *
+ * A provider's overbooking policy lets appointments be booked over others
+ * already in their calendar: up to `max_parallel` appointments at once,
+ * optionally only for some appointment types and within some hours of the
+ * day. Overbooking is never automatic: the booking must ask for it, and the
+ * user needs the `overbook` permission on appointments.
+ */
+
+use chrono::{DateTime, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
+
+use super::AppointmentType;
+
+/// Most appointments a policy allows at the same time
+pub const MAX_PARALLEL_BOOKINGS: i32 = 5;
+
+/// Overbooking policy of a provider (API output)
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct OverbookingPolicy {
+    pub provider_id: Uuid,
+    pub provider_name: String,
+    /// Most appointments of the provider at the same time, the new one
+    /// included
+    pub max_parallel: i32,
+    /// Types that may be overbooked; empty: any
+    pub appointment_types: Vec<AppointmentType>,
+    /// Hours of the day (Europe/Rome) overbooking is allowed in; `None`:
+    /// the whole day
+    pub start_time: Option<NaiveTime>,
+    pub end_time: Option<NaiveTime>,
+    pub created_by: Option<Uuid>,
+    pub updated_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl OverbookingPolicy {
+    /// Whether an appointment may be overbooked over `overlapping` others
+    ///
+    /// `local_start` and `local_end` are the appointment's times of day in
+    /// Europe/Rome.
+    pub fn allows(
+        &self,
+        appointment_type: AppointmentType,
+        local_start: NaiveTime,
+        local_end: NaiveTime,
+        overlapping: usize,
+    ) -> bool {
+        let type_allowed =
+            self.appointment_types.is_empty() || self.appointment_types.contains(&appointment_type);
+        let within_hours = match (self.start_time, self.end_time) {
+            (Some(start), Some(end)) => {
+                local_start >= start && local_end <= end && local_start < local_end
+            }
+            _ => true,
+        };
+
+        type_allowed && within_hours && overlapping < self.max_parallel as usize
+    }
+}
+
+/// Request body for PUT /api/v1/appointments/overbooking-policies/:provider_id
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[validate(schema(function = "validate_policy_hours"))]
+pub struct SetOverbookingPolicyRequest {
+    #[validate(range(
+        min = 2,
+        max = 5,
+        message = "Parallel bookings must be between 2 and 5"
+    ))]
+    pub max_parallel: i32,
+
+    #[serde(default)]
+    pub appointment_types: Vec<AppointmentType>,
+
+    pub start_time: Option<NaiveTime>,
+    pub end_time: Option<NaiveTime>,
+}
+
+fn validate_policy_hours(request: &SetOverbookingPolicyRequest) -> Result<(), ValidationError> {
+    match (request.start_time, request.end_time) {
+        (None, None) => Ok(()),
+        (Some(start), Some(end)) if start < end => Ok(()),
+        (Some(_), Some(_)) => {
+            Err(ValidationError::new("hours")
+                .with_message("start_time must be before end_time".into()))
+        }
+        _ => Err(ValidationError::new("hours")
+            .with_message("start_time and end_time must be given together".into())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(
+        appointment_types: Vec<AppointmentType>,
+        hours: Option<(NaiveTime, NaiveTime)>,
+    ) -> OverbookingPolicy {
+        OverbookingPolicy {
+            provider_id: Uuid::new_v4(),
+            provider_name: "Mario Rossi".to_string(),
+            max_parallel: 2,
+            appointment_types,
+            start_time: hours.map(|(start, _)| start),
+            end_time: hours.map(|(_, end)| end),
+            created_by: None,
+            updated_by: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn time(h: u32, m: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(h, m, 0).unwrap()
+    }
+
+    #[test]
+    fn test_policy_allows_types_hours_and_parallel_bookings() {
+        let any = policy(Vec::new(), None);
+        assert!(any.allows(AppointmentType::FollowUp, time(9, 0), time(9, 30), 1));
+        assert!(!any.allows(AppointmentType::FollowUp, time(9, 0), time(9, 30), 2));
+
+        let urgent_mornings = policy(
+            vec![AppointmentType::Urgent],
+            Some((time(8, 0), time(12, 0))),
+        );
+        assert!(urgent_mornings.allows(AppointmentType::Urgent, time(11, 30), time(12, 0), 1));
+        assert!(!urgent_mornings.allows(AppointmentType::FollowUp, time(9, 0), time(9, 30), 1));
+        assert!(!urgent_mornings.allows(AppointmentType::Urgent, time(11, 45), time(12, 15), 1));
+        assert!(!urgent_mornings.allows(AppointmentType::Urgent, time(7, 30), time(8, 0), 1));
+    }
+
+    #[test]
+    fn test_set_policy_request_validation() {
+        let request = |start_time, end_time| SetOverbookingPolicyRequest {
+            max_parallel: 2,
+            appointment_types: Vec::new(),
+            start_time,
+            end_time,
+        };
+
+        assert!(request(None, None).validate().is_ok());
+        assert!(request(Some(time(8, 0)), Some(time(12, 0)))
+            .validate()
+            .is_ok());
+        assert!(request(Some(time(12, 0)), Some(time(8, 0)))
+            .validate()
+            .is_err());
+        assert!(request(Some(time(8, 0)), None).validate().is_err());
+
+        let mut too_many = request(None, None);
+        too_many.max_parallel = MAX_PARALLEL_BOOKINGS + 1;
+        assert!(too_many.validate().is_err());
+        too_many.max_parallel = 1;
+        assert!(too_many.validate().is_err());
+    }
+}
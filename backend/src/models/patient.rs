@@ -1,7 +1,10 @@
 // Patient model with comprehensive medical and demographic information
 // All PHI/PII fields are encrypted using AES-256-GCM before database storage
 
-use crate::utils::{encryption::EncryptionKey, FiscalCodeValidator, PhoneValidator};
+use crate::utils::{
+    encryption::{DeterministicField, EncryptionKey},
+    text_search, FiscalCodeValidator, PhoneValidator,
+};
 use anyhow::{Context, Result};
 use chrono::{NaiveDate, DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -182,7 +185,7 @@ fn validate_fiscal_code(fiscal_code: &str) -> Result<(), validator::ValidationEr
 }
 
 /// Validate phone number format
-fn validate_phone(phone: &str) -> Result<(), validator::ValidationError> {
+pub(crate) fn validate_phone(phone: &str) -> Result<(), validator::ValidationError> {
     if !PhoneValidator::validate(phone) {
         return Err(validator::ValidationError::new("invalid_phone"));
     }
@@ -295,6 +298,58 @@ pub struct UpdatePatientRequest {
 
     #[validate(length(max = 5000))]
     pub notes: Option<String>,
+
+    /// `updated_at` of the patient as read; the update is refused with 409
+    /// if it changed since (same as `If-Match`)
+    pub expected_version: Option<DateTime<Utc>>,
+}
+
+/// Names of the clinical fields set in a patient request
+///
+/// Used to keep roles without clinical access (RECEPTIONIST) to demographics.
+fn clinical_fields_set(
+    blood_type: &Option<String>,
+    allergies: &Option<Vec<String>>,
+    chronic_conditions: &Option<Vec<String>>,
+    current_medications: &Option<Vec<Medication>>,
+    notes: &Option<String>,
+) -> Vec<&'static str> {
+    [
+        ("blood_type", blood_type.is_some()),
+        ("allergies", allergies.is_some()),
+        ("chronic_conditions", chronic_conditions.is_some()),
+        ("current_medications", current_medications.is_some()),
+        ("notes", notes.is_some()),
+    ]
+    .into_iter()
+    .filter_map(|(name, set)| set.then_some(name))
+    .collect()
+}
+
+impl CreatePatientRequest {
+    /// Clinical fields present in the request
+    pub fn clinical_fields(&self) -> Vec<&'static str> {
+        clinical_fields_set(
+            &self.blood_type,
+            &self.allergies,
+            &self.chronic_conditions,
+            &self.current_medications,
+            &self.notes,
+        )
+    }
+}
+
+impl UpdatePatientRequest {
+    /// Clinical fields present in the request
+    pub fn clinical_fields(&self) -> Vec<&'static str> {
+        clinical_fields_set(
+            &self.blood_type,
+            &self.allergies,
+            &self.chronic_conditions,
+            &self.current_medications,
+            &self.notes,
+        )
+    }
 }
 
 /// Patient search filters
@@ -303,6 +358,10 @@ pub struct PatientSearchFilter {
     pub query: Option<String>,              // Full-text search query
     pub status: Option<PatientStatus>,
     pub gender: Option<Gender>,
+    pub fiscal_code: Option<String>,        // Exact match (blind index)
+    pub last_name: Option<String>,          // Prefix match (blind index)
+    pub phone: Option<String>,              // Exact match on either phone (blind index)
+    pub email: Option<String>,              // Exact match (blind index)
     pub min_age: Option<u32>,
     pub max_age: Option<u32>,
     pub has_allergies: Option<bool>,
@@ -311,6 +370,252 @@ pub struct PatientSearchFilter {
     pub offset: Option<i64>,
 }
 
+/// Most patients an autocomplete query returns
+pub const AUTOCOMPLETE_MAX_RESULTS: i64 = 10;
+
+/// Patient autocomplete query (`GET /patients/autocomplete`)
+#[derive(Debug, Clone, Deserialize)]
+pub struct PatientAutocompleteQuery {
+    /// Part of a name, fiscal code or phone number; several words must all match
+    pub q: String,
+    pub status: Option<PatientStatus>,
+    /// At most `AUTOCOMPLETE_MAX_RESULTS` (the default)
+    pub limit: Option<i64>,
+}
+
+/// Unique index on the (deterministically encrypted) fiscal code of active patients
+const FISCAL_CODE_UNIQUE_INDEX: &str = "idx_patients_fiscal_code_unique";
+
+/// Whether a failed insert or update of a patient clashes with the fiscal
+/// code of another active patient
+pub fn is_fiscal_code_conflict(error: &anyhow::Error) -> bool {
+    matches!(
+        error.downcast_ref::<sqlx::Error>(),
+        Some(sqlx::Error::Database(e)) if e.constraint() == Some(FISCAL_CODE_UNIQUE_INDEX)
+    )
+}
+
+/// Blind index field labels, so equal values of different fields hash apart
+const FISCAL_CODE_BIDX_FIELD: &str = "patients.fiscal_code";
+const LAST_NAME_BIDX_FIELD: &str = "patients.last_name";
+const PHONE_BIDX_FIELD: &str = "patients.phone";
+const EMAIL_BIDX_FIELD: &str = "patients.email";
+const AUTOCOMPLETE_BIDX_FIELD: &str = "patients.autocomplete";
+
+/// Shortest last name prefix that can be looked up
+pub const LAST_NAME_PREFIX_MIN_CHARS: usize = 2;
+
+/// Longest last name prefix indexed; longer prefixes are looked up by their
+/// first characters and checked after decryption
+pub const LAST_NAME_PREFIX_MAX_CHARS: usize = 12;
+
+/// Blind indexes of the lookup fields of a patient (`*_bidx` columns)
+///
+/// Values are normalized before hashing, so lookups ignore case, spacing
+/// and formatting: fiscal codes are uppercased without spaces, last names
+/// keep only letters and digits with accents folded ("D'Angelo" and
+/// "dangelo" match), phones keep only digits without the Italian country
+/// prefix, emails are trimmed and lowercased.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PatientBlindIndexes {
+    pub fiscal_code: Option<String>,
+    pub last_name: Option<String>,
+    pub last_name_prefixes: Vec<String>,
+    pub phones: Vec<String>,
+    pub email: Option<String>,
+    /// Trigrams of the names, fiscal code and phones, for autocomplete
+    pub trigrams: Vec<String>,
+}
+
+impl PatientBlindIndexes {
+    /// Blind indexes of the given plaintext values
+    pub fn compute(
+        key: &EncryptionKey,
+        first_name: &str,
+        last_name: &str,
+        fiscal_code: Option<&str>,
+        phones: &[Option<&str>],
+        email: Option<&str>,
+    ) -> Self {
+        let last_name = normalize_last_name(last_name);
+
+        let mut phone_indexes: Vec<String> = Vec::new();
+        for phone in phones.iter().flatten() {
+            if let Some(index) = Self::phone_lookup(key, phone) {
+                if !phone_indexes.contains(&index) {
+                    phone_indexes.push(index);
+                }
+            }
+        }
+
+        let mut terms = vec![normalize_last_name(first_name), last_name.clone()];
+        terms.extend(fiscal_code.map(normalize_last_name));
+        terms.extend(phones.iter().flatten().filter_map(|phone| normalize_phone(phone)));
+        let mut trigrams: Vec<String> = terms
+            .iter()
+            .flat_map(|term| key.trigram_blind_indexes(AUTOCOMPLETE_BIDX_FIELD, term))
+            .collect();
+        trigrams.sort();
+        trigrams.dedup();
+
+        Self {
+            fiscal_code: fiscal_code.and_then(|value| Self::fiscal_code_lookup(key, value)),
+            last_name: (!last_name.is_empty())
+                .then(|| key.field_blind_index(LAST_NAME_BIDX_FIELD, &last_name)),
+            last_name_prefixes: key.prefix_blind_indexes(
+                LAST_NAME_BIDX_FIELD,
+                &last_name,
+                LAST_NAME_PREFIX_MIN_CHARS,
+                LAST_NAME_PREFIX_MAX_CHARS,
+            ),
+            phones: phone_indexes,
+            email: email.and_then(|value| Self::email_lookup(key, value)),
+            trigrams,
+        }
+    }
+
+    /// Blind indexes of a stored patient, decrypting its lookup fields
+    pub fn of_patient(key: &EncryptionKey, patient: &Patient) -> Result<Self> {
+        let first_name = key.decrypt(&patient.first_name)?;
+        let last_name = key.decrypt(&patient.last_name)?;
+        let fiscal_code = key.decrypt_optional(&patient.fiscal_code)?;
+        let phone_primary = key.decrypt_optional(&patient.phone_primary)?;
+        let phone_secondary = key.decrypt_optional(&patient.phone_secondary)?;
+        let email = key.decrypt_optional(&patient.email)?;
+
+        Ok(Self::compute(
+            key,
+            &first_name,
+            &last_name,
+            fiscal_code.as_deref(),
+            &[phone_primary.as_deref(), phone_secondary.as_deref()],
+            email.as_deref(),
+        ))
+    }
+
+    /// Value to compare with `fiscal_code_bidx`
+    pub fn fiscal_code_lookup(key: &EncryptionKey, fiscal_code: &str) -> Option<String> {
+        let normalized = normalize_fiscal_code(fiscal_code);
+        (!normalized.is_empty())
+            .then(|| key.field_blind_index(FISCAL_CODE_BIDX_FIELD, &normalized))
+    }
+
+    /// Value to compare with `last_name_bidx`
+    pub fn last_name_lookup(key: &EncryptionKey, last_name: &str) -> Option<String> {
+        let normalized = normalize_last_name(last_name);
+        (!normalized.is_empty()).then(|| key.field_blind_index(LAST_NAME_BIDX_FIELD, &normalized))
+    }
+
+    /// Value to look for in `last_name_prefix_bidx`; none when the prefix
+    /// is too short to be indexed
+    pub fn last_name_prefix_lookup(key: &EncryptionKey, prefix: &str) -> Option<String> {
+        let normalized: String = normalize_last_name(prefix)
+            .chars()
+            .take(LAST_NAME_PREFIX_MAX_CHARS)
+            .collect();
+        (normalized.chars().count() >= LAST_NAME_PREFIX_MIN_CHARS)
+            .then(|| key.field_blind_index(LAST_NAME_BIDX_FIELD, &normalized))
+    }
+
+    /// Value to look for in `phone_bidx`
+    pub fn phone_lookup(key: &EncryptionKey, phone: &str) -> Option<String> {
+        normalize_phone(phone).map(|normalized| key.field_blind_index(PHONE_BIDX_FIELD, &normalized))
+    }
+
+    /// Value to compare with `email_bidx`
+    pub fn email_lookup(key: &EncryptionKey, email: &str) -> Option<String> {
+        let normalized = email.trim().to_lowercase();
+        (!normalized.is_empty()).then(|| key.field_blind_index(EMAIL_BIDX_FIELD, &normalized))
+    }
+
+    /// Trigrams that `search_trigram_bidx` must contain for a patient to
+    /// match an autocomplete query; empty when no term of the query is long
+    /// enough to have any
+    pub fn autocomplete_lookup(key: &EncryptionKey, query: &str) -> Vec<String> {
+        let mut trigrams: Vec<String> = autocomplete_terms(query)
+            .iter()
+            .flat_map(|term| key.trigram_blind_indexes(AUTOCOMPLETE_BIDX_FIELD, term))
+            .collect();
+        trigrams.sort();
+        trigrams.dedup();
+        trigrams
+    }
+}
+
+/// Terms of an autocomplete query, normalized like the values they are
+/// looked up in: names and fiscal codes like last names, numbers in
+/// international form like phones
+pub fn autocomplete_terms(query: &str) -> Vec<String> {
+    query
+        .split_whitespace()
+        .filter_map(|word| {
+            let international = word.starts_with('+') || word.starts_with("00");
+            if international && word[1..].chars().all(|c| c.is_ascii_digit()) {
+                normalize_phone(word)
+            } else {
+                Some(normalize_last_name(word))
+            }
+        })
+        .filter(|term| !term.is_empty())
+        .collect()
+}
+
+/// Whether a patient matches every term of an autocomplete query (names,
+/// fiscal code or phones contain it)
+pub fn matches_autocomplete(patient: &PatientDto, terms: &[String]) -> bool {
+    let mut values = vec![
+        normalize_last_name(&patient.first_name),
+        normalize_last_name(&patient.last_name),
+    ];
+    values.extend(patient.fiscal_code.as_deref().map(normalize_last_name));
+    values.extend(
+        [&patient.phone_primary, &patient.phone_secondary]
+            .into_iter()
+            .flatten()
+            .filter_map(|phone| normalize_phone(phone)),
+    );
+
+    terms
+        .iter()
+        .all(|term| values.iter().any(|value| value.contains(term.as_str())))
+}
+
+/// Fiscal code uppercased without spaces
+pub fn normalize_fiscal_code(fiscal_code: &str) -> String {
+    fiscal_code
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_uppercase()
+}
+
+/// Last name reduced to lowercase letters and digits, accents folded
+pub fn normalize_last_name(last_name: &str) -> String {
+    text_search::normalize(last_name)
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .collect()
+}
+
+/// Phone number reduced to its digits, without the Italian country prefix
+/// when written in international form (+39, 0039)
+fn normalize_phone(phone: &str) -> Option<String> {
+    let trimmed = phone.trim();
+    let digits: String = trimmed.chars().filter(|c| c.is_ascii_digit()).collect();
+    let international = trimmed.starts_with('+') || trimmed.starts_with("00");
+
+    let national = if international {
+        digits
+            .strip_prefix("0039")
+            .or_else(|| digits.strip_prefix("39").filter(|_| trimmed.starts_with('+')))
+            .unwrap_or(&digits)
+    } else {
+        &digits
+    };
+
+    (!national.is_empty()).then(|| national.to_string())
+}
+
 impl Patient {
     /// Decrypt patient data to DTO
     pub fn decrypt(&self, key: &EncryptionKey) -> Result<PatientDto> {
@@ -411,7 +716,8 @@ impl Patient {
         let encrypted_last_name = key.encrypt(&data.last_name)?;
         let encrypted_middle_name = key.encrypt_optional(&data.middle_name)?;
         let encrypted_dob = key.encrypt(&data.date_of_birth.to_string())?;
-        let encrypted_fiscal_code = key.encrypt_optional(&data.fiscal_code)?;
+        let encrypted_fiscal_code =
+            key.encrypt_deterministic_optional(DeterministicField::FiscalCode, &data.fiscal_code)?;
 
         let encrypted_phone_primary = key.encrypt_optional(&data.phone_primary)?;
         let encrypted_phone_secondary = key.encrypt_optional(&data.phone_secondary)?;
@@ -444,6 +750,15 @@ impl Patient {
 
         let encrypted_notes = key.encrypt_optional(&data.notes)?;
 
+        let blind_indexes = PatientBlindIndexes::compute(
+            key,
+            &data.first_name,
+            &data.last_name,
+            data.fiscal_code.as_deref(),
+            &[data.phone_primary.as_deref(), data.phone_secondary.as_deref()],
+            data.email.as_deref(),
+        );
+
         let patient = sqlx::query_as::<_, Patient>(
             r#"
             INSERT INTO patients (
@@ -453,9 +768,12 @@ impl Patient {
                 blood_type, allergies, chronic_conditions, current_medications,
                 health_card_expire, photo_url,
                 notes,
-                created_by, updated_by
+                created_by, updated_by,
+                fiscal_code_bidx, last_name_bidx, last_name_prefix_bidx, phone_bidx, email_bidx,
+                search_trigram_bidx
             )
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21,
+                    $22, $23, $24, $25, $26, $27)
             RETURNING *
             "#,
         )
@@ -480,6 +798,12 @@ impl Patient {
         .bind(encrypted_notes)
         .bind(created_by_id)
         .bind(created_by_id)
+        .bind(blind_indexes.fiscal_code)
+        .bind(blind_indexes.last_name)
+        .bind(blind_indexes.last_name_prefixes)
+        .bind(blind_indexes.phones)
+        .bind(blind_indexes.email)
+        .bind(blind_indexes.trigrams)
         .fetch_one(executor)
         .await
         .context("Failed to create patient")?;
@@ -531,6 +855,39 @@ impl Patient {
     where
         E: sqlx::Executor<'e, Database = sqlx::Postgres>,
     {
+        // Blind indexes of the lookup fields after the update
+        let first_name = match &data.first_name {
+            Some(value) => value.clone(),
+            None => key.decrypt(&existing.first_name)?,
+        };
+        let last_name = match &data.last_name {
+            Some(value) => value.clone(),
+            None => key.decrypt(&existing.last_name)?,
+        };
+        let fiscal_code = match &data.fiscal_code {
+            Some(value) => Some(value.clone()),
+            None => key.decrypt_optional(&existing.fiscal_code)?,
+        };
+        let phone_primary = match &data.phone_primary {
+            Some(value) => Some(value.clone()),
+            None => key.decrypt_optional(&existing.phone_primary)?,
+        };
+        let phone_secondary = match &data.phone_secondary {
+            Some(value) => Some(value.clone()),
+            None => key.decrypt_optional(&existing.phone_secondary)?,
+        };
+        let email = match &data.email {
+            Some(value) => Some(value.clone()),
+            None => key.decrypt_optional(&existing.email)?,
+        };
+        let blind_indexes = PatientBlindIndexes::compute(
+            key,
+            &first_name,
+            &last_name,
+            fiscal_code.as_deref(),
+            &[phone_primary.as_deref(), phone_secondary.as_deref()],
+            email.as_deref(),
+        );
 
         // Apply updates to existing patient (only update provided fields)
         let updated_first_name = if let Some(ref fn_val) = data.first_name {
@@ -558,7 +915,7 @@ impl Patient {
         };
 
         let updated_fiscal_code = if data.fiscal_code.is_some() {
-            key.encrypt_optional(&data.fiscal_code)?
+            key.encrypt_deterministic_optional(DeterministicField::FiscalCode, &data.fiscal_code)?
         } else {
             existing.fiscal_code
         };
@@ -634,7 +991,9 @@ impl Patient {
                 preferred_contact_method = $10, address = $11, emergency_contact = $12,
                 blood_type = $13, allergies = $14, chronic_conditions = $15,
                 current_medications = $16, health_card_expire = $17, photo_url = $18,
-                notes = $19, updated_by = $20, updated_at = NOW()
+                notes = $19, updated_by = $20, updated_at = NOW(),
+                fiscal_code_bidx = $22, last_name_bidx = $23, last_name_prefix_bidx = $24,
+                phone_bidx = $25, email_bidx = $26, search_trigram_bidx = $27
             WHERE id = $21
             RETURNING *
             "#,
@@ -660,6 +1019,12 @@ impl Patient {
         .bind(updated_notes)
         .bind(updated_by_id)
         .bind(id)
+        .bind(blind_indexes.fiscal_code)
+        .bind(blind_indexes.last_name)
+        .bind(blind_indexes.last_name_prefixes)
+        .bind(blind_indexes.phones)
+        .bind(blind_indexes.email)
+        .bind(blind_indexes.trigrams)
         .fetch_one(executor)
         .await
         .context("Failed to update patient")?;
@@ -667,16 +1032,22 @@ impl Patient {
         Ok(patient)
     }
 
-    /// Delete patient (soft delete by setting status to INACTIVE)
-    pub async fn soft_delete<'e, E>(executor: E, id: Uuid) -> Result<()>
+    /// Delete patient (soft delete into the recycle bin)
+    ///
+    /// The row keeps its status and is hidden by RLS until restored; the
+    /// transaction must name it with `target_trash_record` first.
+    pub async fn soft_delete<'e, E>(executor: E, id: Uuid, deleted_by: Uuid) -> Result<()>
     where
         E: sqlx::Executor<'e, Database = sqlx::Postgres>,
     {
-        sqlx::query("UPDATE patients SET status = 'INACTIVE', updated_at = NOW() WHERE id = $1")
-            .bind(id)
-            .execute(executor)
-            .await
-            .context("Failed to soft delete patient")?;
+        sqlx::query(
+            "UPDATE patients SET deleted_at = NOW(), deleted_by = $2 WHERE id = $1 AND deleted_at IS NULL",
+        )
+        .bind(id)
+        .bind(deleted_by)
+        .execute(executor)
+        .await
+        .context("Failed to soft delete patient")?;
 
         Ok(())
     }
@@ -780,6 +1151,93 @@ mod tests {
         assert!(request.validate().is_ok());
     }
 
+    #[test]
+    fn test_clinical_fields() {
+        let mut request = CreatePatientRequest {
+            first_name: "John".to_string(),
+            last_name: "Doe".to_string(),
+            middle_name: None,
+            date_of_birth: NaiveDate::from_ymd_opt(1990, 1, 1).unwrap(),
+            gender: Gender::M,
+            fiscal_code: None,
+            phone_primary: Some("+1234567890".to_string()),
+            phone_secondary: None,
+            email: None,
+            preferred_contact_method: None,
+            address: None,
+            emergency_contact: None,
+            blood_type: None,
+            allergies: None,
+            chronic_conditions: None,
+            current_medications: None,
+            health_card_expire: None,
+            photo_url: None,
+            notes: None,
+        };
+        assert!(request.clinical_fields().is_empty());
+
+        request.allergies = Some(vec!["Penicillin".to_string()]);
+        request.notes = Some("Prefers morning appointments".to_string());
+        assert_eq!(request.clinical_fields(), vec!["allergies", "notes"]);
+    }
+
+    #[test]
+    fn test_patient_blind_indexes() {
+        std::env::set_var("ENCRYPTION_KEY", "MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWY=");
+        let key = EncryptionKey::from_env().unwrap();
+
+        let indexes = PatientBlindIndexes::compute(
+            &key,
+            "Mario",
+            "D'Angelò",
+            Some("rssmra85t10 a562s"),
+            &[Some("+39 333 123 4567"), Some("0039 333 1234567")],
+            Some(" Mario.Rossi@Example.com "),
+        );
+
+        assert_eq!(indexes.fiscal_code, PatientBlindIndexes::fiscal_code_lookup(&key, "RSSMRA85T10A562S"));
+        assert_eq!(indexes.last_name, PatientBlindIndexes::last_name_lookup(&key, "dangelo"));
+        assert_eq!(indexes.last_name_prefixes.len(), "dangelo".len() - 1);
+        assert!(indexes
+            .last_name_prefixes
+            .contains(&PatientBlindIndexes::last_name_prefix_lookup(&key, "D'An").unwrap()));
+        assert_eq!(indexes.phones, vec![PatientBlindIndexes::phone_lookup(&key, "3331234567").unwrap()]);
+        assert_eq!(indexes.email, PatientBlindIndexes::email_lookup(&key, "mario.rossi@example.com"));
+
+        assert_eq!(PatientBlindIndexes::last_name_prefix_lookup(&key, "D'"), None);
+        assert_ne!(
+            PatientBlindIndexes::phone_lookup(&key, "0039 06 1234567"),
+            PatientBlindIndexes::phone_lookup(&key, "39 06 1234567")
+        );
+    }
+
+    #[test]
+    fn test_patient_autocomplete_trigrams() {
+        let key =
+            EncryptionKey::from_base64("MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWY=").unwrap();
+
+        let indexes = PatientBlindIndexes::compute(
+            &key,
+            "Mario",
+            "D'Angelò",
+            Some("RSSMRA85T10A562S"),
+            &[Some("+39 333 123 4567"), None],
+            None,
+        );
+
+        for query in ["angel", "mar dang", "t10a", "1234567", "+393331234567"] {
+            let lookup = PatientBlindIndexes::autocomplete_lookup(&key, query);
+            assert!(!lookup.is_empty(), "{}", query);
+            assert!(lookup.iter().all(|t| indexes.trigrams.contains(t)), "{}", query);
+        }
+        assert!(!PatientBlindIndexes::autocomplete_lookup(&key, "rossi")
+            .iter()
+            .all(|t| indexes.trigrams.contains(t)));
+        assert!(PatientBlindIndexes::autocomplete_lookup(&key, "ma d'").is_empty());
+
+        assert_eq!(autocomplete_terms("  D'Ang  +39 333 "), vec!["dang", "333"]);
+    }
+
     #[test]
     fn test_validate_blood_type() {
         // Valid blood types
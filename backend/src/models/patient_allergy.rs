@@ -0,0 +1,267 @@
+/*!
+ * Patient Allergy Model
+ *
+ * Structured allergies and intolerances of a patient. Substance, reaction
+ * and notes are encrypted at rest; the coded fields (category, severity,
+ * status) stay in clear so they can be filtered on.
+ */
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::utils::encryption::EncryptionKey;
+
+/// Immune-mediated allergy or non-immune intolerance
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AllergyType {
+    #[default]
+    Allergy,
+    Intolerance,
+}
+
+impl AllergyType {
+    /// Convert to database string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AllergyType::Allergy => "ALLERGY",
+            AllergyType::Intolerance => "INTOLERANCE",
+        }
+    }
+}
+
+/// Kind of substance
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AllergyCategory {
+    Drug,
+    Food,
+    /// Pollen, dust mites, latex, insect stings...
+    Environmental,
+    Other,
+}
+
+impl AllergyCategory {
+    /// Convert to database string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AllergyCategory::Drug => "DRUG",
+            AllergyCategory::Food => "FOOD",
+            AllergyCategory::Environmental => "ENVIRONMENTAL",
+            AllergyCategory::Other => "OTHER",
+        }
+    }
+}
+
+/// Severity of the worst known reaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AllergySeverity {
+    Mild,
+    Moderate,
+    Severe,
+    /// Anaphylaxis or other life-threatening reaction
+    LifeThreatening,
+}
+
+impl AllergySeverity {
+    /// Convert to database string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AllergySeverity::Mild => "MILD",
+            AllergySeverity::Moderate => "MODERATE",
+            AllergySeverity::Severe => "SEVERE",
+            AllergySeverity::LifeThreatening => "LIFE_THREATENING",
+        }
+    }
+}
+
+/// Clinical status of an allergy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AllergyStatus {
+    Active,
+    Inactive,
+    /// The patient no longer reacts (e.g. after desensitization)
+    Resolved,
+    /// Recorded by mistake; kept for the record
+    EnteredInError,
+}
+
+impl AllergyStatus {
+    /// Convert to database string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AllergyStatus::Active => "ACTIVE",
+            AllergyStatus::Inactive => "INACTIVE",
+            AllergyStatus::Resolved => "RESOLVED",
+            AllergyStatus::EnteredInError => "ENTERED_IN_ERROR",
+        }
+    }
+}
+
+/// Allergy row (encrypted)
+#[derive(Debug, Clone, FromRow)]
+pub struct PatientAllergy {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub allergy_type: String,
+    pub category: String,
+    pub substance: String,        // 🔒 Encrypted
+    pub reaction: Option<String>, // 🔒 Encrypted
+    pub severity: Option<String>,
+    pub onset_date: Option<NaiveDate>,
+    pub status: String,
+    pub notes: Option<String>, // 🔒 Encrypted
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_by: Option<Uuid>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl PatientAllergy {
+    /// Decrypt into the API representation
+    pub fn decrypt(&self, key: &EncryptionKey) -> Result<PatientAllergyResponse> {
+        Ok(PatientAllergyResponse {
+            id: self.id,
+            patient_id: self.patient_id,
+            allergy_type: self.allergy_type.clone(),
+            category: self.category.clone(),
+            substance: key
+                .decrypt(&self.substance)
+                .context("Failed to decrypt allergy substance")?,
+            reaction: key
+                .decrypt_optional(&self.reaction)
+                .context("Failed to decrypt allergy reaction")?,
+            severity: self.severity.clone(),
+            onset_date: self.onset_date,
+            status: self.status.clone(),
+            notes: key
+                .decrypt_optional(&self.notes)
+                .context("Failed to decrypt allergy notes")?,
+            created_by: self.created_by,
+            created_at: self.created_at,
+            updated_by: self.updated_by,
+            updated_at: self.updated_at,
+        })
+    }
+}
+
+/// Allergy (API output, decrypted)
+#[derive(Debug, Clone, Serialize)]
+pub struct PatientAllergyResponse {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub allergy_type: String,
+    pub category: String,
+    pub substance: String,
+    pub reaction: Option<String>,
+    pub severity: Option<String>,
+    pub onset_date: Option<NaiveDate>,
+    pub status: String,
+    pub notes: Option<String>,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_by: Option<Uuid>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Active allergy as shown with a visit and in document templates
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AllergySummary {
+    pub allergy_type: String,
+    pub category: String,
+    pub substance: String,
+    pub reaction: Option<String>,
+    pub severity: Option<String>,
+}
+
+impl From<PatientAllergyResponse> for AllergySummary {
+    fn from(allergy: PatientAllergyResponse) -> Self {
+        Self {
+            allergy_type: allergy.allergy_type,
+            category: allergy.category,
+            substance: allergy.substance,
+            reaction: allergy.reaction,
+            severity: allergy.severity,
+        }
+    }
+}
+
+/// Request body for POST /api/v1/patients/:id/allergies
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct CreatePatientAllergyRequest {
+    #[serde(default)]
+    pub allergy_type: AllergyType,
+    pub category: AllergyCategory,
+    #[validate(length(min = 1, max = 255, message = "Substance must be 1-255 characters"))]
+    pub substance: String,
+    #[validate(length(max = 1000))]
+    pub reaction: Option<String>,
+    pub severity: Option<AllergySeverity>,
+    pub onset_date: Option<NaiveDate>,
+    /// Defaults to ACTIVE
+    pub status: Option<AllergyStatus>,
+    #[validate(length(max = 5000))]
+    pub notes: Option<String>,
+}
+
+/// Request body for PUT /api/v1/patients/:id/allergies/:allergy_id
+///
+/// Absent fields are left unchanged.
+#[derive(Debug, Clone, Default, Deserialize, Validate)]
+pub struct UpdatePatientAllergyRequest {
+    pub allergy_type: Option<AllergyType>,
+    pub category: Option<AllergyCategory>,
+    #[validate(length(min = 1, max = 255, message = "Substance must be 1-255 characters"))]
+    pub substance: Option<String>,
+    #[validate(length(max = 1000))]
+    pub reaction: Option<String>,
+    pub severity: Option<AllergySeverity>,
+    pub onset_date: Option<NaiveDate>,
+    pub status: Option<AllergyStatus>,
+    #[validate(length(max = 5000))]
+    pub notes: Option<String>,
+}
+
+/// Query parameters for GET /api/v1/patients/:id/allergies
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ListPatientAllergiesQuery {
+    pub status: Option<AllergyStatus>,
+    pub category: Option<AllergyCategory>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_allergy_request_defaults_and_validation() {
+        let request: CreatePatientAllergyRequest = serde_json::from_str(
+            r#"{"category": "DRUG", "substance": "Penicillin", "severity": "LIFE_THREATENING"}"#,
+        )
+        .unwrap();
+        assert!(request.validate().is_ok());
+        assert_eq!(request.allergy_type, AllergyType::Allergy);
+        assert_eq!(request.severity, Some(AllergySeverity::LifeThreatening));
+        assert_eq!(request.status, None);
+
+        let empty: CreatePatientAllergyRequest =
+            serde_json::from_str(r#"{"category": "FOOD", "substance": ""}"#).unwrap();
+        assert!(empty.validate().is_err());
+    }
+
+    #[test]
+    fn test_allergy_status_as_str() {
+        assert_eq!(AllergyStatus::EnteredInError.as_str(), "ENTERED_IN_ERROR");
+        assert_eq!(
+            serde_json::to_value(AllergyStatus::EnteredInError).unwrap(),
+            "ENTERED_IN_ERROR"
+        );
+        assert_eq!(AllergyCategory::Environmental.as_str(), "ENVIRONMENTAL");
+    }
+}
@@ -0,0 +1,248 @@
+/*!
+ * Patient Attachment Model
+ *
+ * External documents on the patient record (old lab reports, referral
+ * letters, scans), optionally linked to a visit. The content is stored
+ * through `FileUploadService`, encrypted at rest; this model holds the
+ * clinical metadata and the result of the background OCR (encrypted too).
+ */
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+/// Kind of external document
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AttachmentCategory {
+    LabReport,
+    ReferralLetter,
+    ImagingReport,
+    DischargeSummary,
+    SpecialistReport,
+    /// Any other paper document scanned into the record
+    ScannedDocument,
+    Other,
+}
+
+impl AttachmentCategory {
+    /// Convert to database string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AttachmentCategory::LabReport => "LAB_REPORT",
+            AttachmentCategory::ReferralLetter => "REFERRAL_LETTER",
+            AttachmentCategory::ImagingReport => "IMAGING_REPORT",
+            AttachmentCategory::DischargeSummary => "DISCHARGE_SUMMARY",
+            AttachmentCategory::SpecialistReport => "SPECIALIST_REPORT",
+            AttachmentCategory::ScannedDocument => "SCANNED_DOCUMENT",
+            AttachmentCategory::Other => "OTHER",
+        }
+    }
+
+    /// Parse from database string (case-insensitive, for multipart fields)
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.trim().to_uppercase().as_str() {
+            "LAB_REPORT" => Some(AttachmentCategory::LabReport),
+            "REFERRAL_LETTER" => Some(AttachmentCategory::ReferralLetter),
+            "IMAGING_REPORT" => Some(AttachmentCategory::ImagingReport),
+            "DISCHARGE_SUMMARY" => Some(AttachmentCategory::DischargeSummary),
+            "SPECIALIST_REPORT" => Some(AttachmentCategory::SpecialistReport),
+            "SCANNED_DOCUMENT" => Some(AttachmentCategory::ScannedDocument),
+            "OTHER" => Some(AttachmentCategory::Other),
+            _ => None,
+        }
+    }
+}
+
+/// Progress of an attachment's text recognition
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum OcrStatus {
+    Pending,
+    Processing,
+    Completed,
+    Failed,
+    /// The configured engine cannot read this file type
+    Skipped,
+}
+
+impl OcrStatus {
+    /// Convert to database string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OcrStatus::Pending => "PENDING",
+            OcrStatus::Processing => "PROCESSING",
+            OcrStatus::Completed => "COMPLETED",
+            OcrStatus::Failed => "FAILED",
+            OcrStatus::Skipped => "SKIPPED",
+        }
+    }
+}
+
+/// Fields suggested from an attachment's recognized text, for the user to
+/// confirm (they are never applied automatically)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OcrSuggestions {
+    pub document_date: Option<NaiveDate>,
+    pub lab_name: Option<String>,
+}
+
+/// Attachment row joined with its file's metadata
+#[derive(Debug, Clone, FromRow)]
+pub struct PatientAttachment {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub visit_id: Option<Uuid>,
+    pub file_id: Uuid,
+    pub category: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub document_date: Option<NaiveDate>,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_by: Option<Uuid>,
+    pub updated_at: DateTime<Utc>,
+    pub ocr_status: String,
+    /// Encrypted
+    pub ocr_text: Option<String>,
+    /// Encrypted JSON of `OcrSuggestions`
+    pub ocr_suggestions: Option<String>,
+    // From uploaded_files
+    pub original_filename: String,
+    pub mime_type: String,
+    pub file_size_bytes: i64,
+}
+
+/// Attachment (API output)
+#[derive(Debug, Clone, Serialize)]
+pub struct PatientAttachmentResponse {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub visit_id: Option<Uuid>,
+    pub category: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub document_date: Option<NaiveDate>,
+    pub original_filename: String,
+    pub mime_type: String,
+    pub file_size_bytes: i64,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub download_url: String,
+    pub ocr_status: String,
+    pub ocr_suggestions: Option<OcrSuggestions>,
+    /// Recognized text, only returned for a single attachment
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ocr_text: Option<String>,
+}
+
+/// The OCR fields are left empty: they are decrypted by the service.
+impl From<PatientAttachment> for PatientAttachmentResponse {
+    fn from(attachment: PatientAttachment) -> Self {
+        Self {
+            download_url: format!(
+                "/api/v1/patients/{}/attachments/{}/download",
+                attachment.patient_id, attachment.id
+            ),
+            id: attachment.id,
+            patient_id: attachment.patient_id,
+            visit_id: attachment.visit_id,
+            category: attachment.category,
+            title: attachment.title,
+            description: attachment.description,
+            document_date: attachment.document_date,
+            original_filename: attachment.original_filename,
+            mime_type: attachment.mime_type,
+            file_size_bytes: attachment.file_size_bytes,
+            created_by: attachment.created_by,
+            created_at: attachment.created_at,
+            updated_at: attachment.updated_at,
+            ocr_status: attachment.ocr_status,
+            ocr_suggestions: None,
+            ocr_text: None,
+        }
+    }
+}
+
+/// Metadata of a new attachment (multipart form fields next to `file`)
+#[derive(Debug, Clone, Validate)]
+pub struct CreatePatientAttachmentRequest {
+    pub category: AttachmentCategory,
+    #[validate(length(min = 1, max = 255, message = "Title must be 1-255 characters"))]
+    pub title: String,
+    #[validate(length(max = 5000))]
+    pub description: Option<String>,
+    pub document_date: Option<NaiveDate>,
+    pub visit_id: Option<Uuid>,
+}
+
+/// Request body for PUT /api/v1/patients/:id/attachments/:attachment_id
+///
+/// Absent fields are left unchanged; `visit_id` cannot be cleared once set
+/// other than by linking another visit.
+#[derive(Debug, Clone, Default, Deserialize, Validate)]
+pub struct UpdatePatientAttachmentRequest {
+    pub category: Option<AttachmentCategory>,
+    #[validate(length(min = 1, max = 255, message = "Title must be 1-255 characters"))]
+    pub title: Option<String>,
+    #[validate(length(max = 5000))]
+    pub description: Option<String>,
+    pub document_date: Option<NaiveDate>,
+    pub visit_id: Option<Uuid>,
+}
+
+/// Query parameters for GET /api/v1/patients/:id/attachments
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ListPatientAttachmentsQuery {
+    pub category: Option<AttachmentCategory>,
+    pub visit_id: Option<Uuid>,
+    /// Search in the title, description and recognized text
+    pub q: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attachment_category_round_trip() {
+        for category in [
+            AttachmentCategory::LabReport,
+            AttachmentCategory::ReferralLetter,
+            AttachmentCategory::ImagingReport,
+            AttachmentCategory::DischargeSummary,
+            AttachmentCategory::SpecialistReport,
+            AttachmentCategory::ScannedDocument,
+            AttachmentCategory::Other,
+        ] {
+            assert_eq!(
+                AttachmentCategory::from_str(category.as_str()),
+                Some(category)
+            );
+        }
+        assert_eq!(
+            AttachmentCategory::from_str(" lab_report "),
+            Some(AttachmentCategory::LabReport)
+        );
+        assert_eq!(AttachmentCategory::from_str("X_RAY"), None);
+    }
+
+    #[test]
+    fn test_update_attachment_request_validation() {
+        let request: UpdatePatientAttachmentRequest = serde_json::from_str(
+            r#"{"category": "REFERRAL_LETTER", "document_date": "2025-11-03"}"#,
+        )
+        .unwrap();
+        assert!(request.validate().is_ok());
+        assert_eq!(request.category, Some(AttachmentCategory::ReferralLetter));
+
+        let empty_title = UpdatePatientAttachmentRequest {
+            title: Some(String::new()),
+            ..Default::default()
+        };
+        assert!(empty_title.validate().is_err());
+    }
+}
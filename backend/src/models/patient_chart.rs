@@ -0,0 +1,60 @@
+/*!
+ * Patient Chart Model
+ *
+ * Aggregated payload of GET /api/v1/patients/:id/chart: everything shown
+ * when a chart is opened, in one response instead of one call per panel.
+ * Each clinical section is `None` when the caller's role may not read it.
+ */
+
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::models::{
+    AppointmentDto, GeneratedDocumentSummary, PatientDto, PatientProblemResponse,
+    PrescriptionResponse, VisitResponse, VitalsTrendPoint,
+};
+
+/// Items per list section when not requested
+pub const DEFAULT_CHART_SECTION_LIMIT: i64 = 5;
+
+/// Query parameters for GET /api/v1/patients/:id/chart
+#[derive(Debug, Clone, Default, Deserialize, Validate)]
+pub struct PatientChartQuery {
+    /// Most recent visits (default 5)
+    #[validate(range(min = 1, max = 50))]
+    pub visits_limit: Option<i64>,
+    /// Next appointments (default 5)
+    #[validate(range(min = 1, max = 50))]
+    pub appointments_limit: Option<i64>,
+    /// Most recent documents (default 5)
+    #[validate(range(min = 1, max = 50))]
+    pub documents_limit: Option<i64>,
+}
+
+/// Chart sections the caller's role may read
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PatientChartSections {
+    /// Recent visits and latest vitals
+    pub visits: bool,
+    pub prescriptions: bool,
+    pub problems: bool,
+    pub appointments: bool,
+    pub documents: bool,
+}
+
+/// Patient chart (API output)
+#[derive(Debug, Clone, Serialize)]
+pub struct PatientChartResponse {
+    pub patient: PatientDto,
+    /// Most recent first
+    pub recent_visits: Option<Vec<VisitResponse>>,
+    pub active_prescriptions: Option<Vec<PrescriptionResponse>>,
+    /// Active and resolved problems
+    pub problems: Option<Vec<PatientProblemResponse>>,
+    /// Scheduled and confirmed, soonest first
+    pub upcoming_appointments: Option<Vec<AppointmentDto>>,
+    /// Vitals of the most recent visit that recorded them
+    pub latest_vitals: Option<VitalsTrendPoint>,
+    /// Most recent first
+    pub recent_documents: Option<Vec<GeneratedDocumentSummary>>,
+}
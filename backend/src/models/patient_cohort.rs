@@ -0,0 +1,359 @@
+/*!
+ * Patient Cohort Model
+ *
+ * A cohort is a set of patients selected by criteria: age range, diagnoses
+ * (visit diagnoses or active problems), active medications and the date of
+ * the last visit. Criteria can be previewed before saving them as a named
+ * cohort; a saved cohort is evaluated again each time it is used, so it
+ * follows the patient records.
+ *
+ * Cohorts feed bulk document generation and notification campaigns.
+ */
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
+
+use crate::models::NotificationType;
+
+/// Highest age accepted in the criteria
+pub const MAX_COHORT_AGE: u32 = 130;
+
+/// Most diagnosis codes and medication names in the criteria
+pub const MAX_COHORT_TERMS: usize = 50;
+
+/// Patients listed in a preview (the count covers the whole cohort)
+pub const COHORT_PREVIEW_LIMIT: usize = 50;
+
+/// Most patients a notification campaign can reach
+pub const MAX_CAMPAIGN_RECIPIENTS: usize = 1000;
+
+/// Criteria selecting the patients of a cohort
+///
+/// Only active patients (not deceased, deleted, merged or anonymized) are
+/// considered. Every given criterion must match; within `diagnosis_codes`
+/// and `medications` any entry matches.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Validate)]
+#[validate(schema(function = "validate_cohort_criteria"))]
+pub struct CohortCriteria {
+    /// Lowest age in years (inclusive)
+    pub min_age: Option<u32>,
+    /// Highest age in years (inclusive)
+    pub max_age: Option<u32>,
+    /// ICD-10 codes or code prefixes ("E11" matches "E11.9") among the
+    /// visit diagnoses or active problems
+    #[serde(default)]
+    pub diagnosis_codes: Vec<String>,
+    /// Medication names (brand or generic, case-insensitive substring) of an
+    /// active prescription
+    #[serde(default)]
+    pub medications: Vec<String>,
+    /// Last visit before this date (patients never visited do not match)
+    pub last_visit_before: Option<NaiveDate>,
+}
+
+impl CohortCriteria {
+    /// Diagnosis codes as uppercase LIKE prefixes
+    pub fn diagnosis_patterns(&self) -> Vec<String> {
+        self.diagnosis_codes
+            .iter()
+            .map(|code| format!("{}%", code.trim().to_uppercase()))
+            .collect()
+    }
+
+    /// Whether an age falls in the age range
+    pub fn matches_age(&self, age: u32) -> bool {
+        self.min_age.is_none_or(|min| age >= min) && self.max_age.is_none_or(|max| age <= max)
+    }
+
+    /// Whether a prescription (brand and generic name) matches the
+    /// medication criterion
+    pub fn matches_medication(&self, medication_name: &str, generic_name: Option<&str>) -> bool {
+        let names = [Some(medication_name), generic_name];
+        self.medications.iter().any(|wanted| {
+            let wanted = wanted.trim().to_lowercase();
+            names
+                .iter()
+                .flatten()
+                .any(|name| name.to_lowercase().contains(&wanted))
+        })
+    }
+}
+
+/// Validator for the criteria as a whole
+fn validate_cohort_criteria(criteria: &CohortCriteria) -> Result<(), ValidationError> {
+    let invalid = |code: &'static str, message: String| {
+        let mut error = ValidationError::new(code);
+        error.message = Some(message.into());
+        Err(error)
+    };
+
+    if criteria.min_age.is_some_and(|age| age > MAX_COHORT_AGE)
+        || criteria.max_age.is_some_and(|age| age > MAX_COHORT_AGE)
+    {
+        return invalid("invalid_age", format!("Ages must be 0-{}", MAX_COHORT_AGE));
+    }
+    if let (Some(min), Some(max)) = (criteria.min_age, criteria.max_age) {
+        if min > max {
+            return invalid(
+                "invalid_age_range",
+                "min_age cannot exceed max_age".to_string(),
+            );
+        }
+    }
+    if criteria.diagnosis_codes.len() > MAX_COHORT_TERMS
+        || criteria.medications.len() > MAX_COHORT_TERMS
+    {
+        return invalid(
+            "too_many_terms",
+            format!(
+                "At most {} diagnosis codes and {} medications",
+                MAX_COHORT_TERMS, MAX_COHORT_TERMS
+            ),
+        );
+    }
+    // Codes become LIKE prefixes, so only letters, digits and dots
+    if let Some(code) = criteria.diagnosis_codes.iter().find(|code| {
+        let code = code.trim();
+        code.is_empty()
+            || code.len() > 10
+            || !code.chars().all(|c| c.is_ascii_alphanumeric() || c == '.')
+    }) {
+        return invalid(
+            "invalid_diagnosis_code",
+            format!("Invalid ICD-10 code '{}'", code),
+        );
+    }
+    if let Some(name) = criteria
+        .medications
+        .iter()
+        .find(|name| !(2..=100).contains(&name.trim().chars().count()))
+    {
+        return invalid(
+            "invalid_medication",
+            format!("Medication '{}' must be 2-100 characters", name),
+        );
+    }
+    Ok(())
+}
+
+/// Saved cohort row
+#[derive(Debug, Clone, FromRow)]
+pub struct PatientCohort {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub criteria: serde_json::Value,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Saved cohort returned by the API
+#[derive(Debug, Clone, Serialize)]
+pub struct PatientCohortResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub criteria: CohortCriteria,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl TryFrom<PatientCohort> for PatientCohortResponse {
+    type Error = String;
+
+    fn try_from(row: PatientCohort) -> Result<Self, Self::Error> {
+        let criteria = serde_json::from_value(row.criteria)
+            .map_err(|e| format!("Invalid cohort criteria: {}", e))?;
+
+        Ok(Self {
+            id: row.id,
+            name: row.name,
+            description: row.description,
+            criteria,
+            created_by: row.created_by,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        })
+    }
+}
+
+/// Request body for POST /api/v1/cohorts
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct CreatePatientCohortRequest {
+    #[validate(length(min = 1, max = 200, message = "Name must be 1-200 characters"))]
+    pub name: String,
+    #[validate(length(max = 2000, message = "Description too long (max 2000 chars)"))]
+    pub description: Option<String>,
+    #[validate(nested)]
+    pub criteria: CohortCriteria,
+}
+
+/// Request body for PUT /api/v1/cohorts/:id
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct UpdatePatientCohortRequest {
+    #[validate(length(min = 1, max = 200, message = "Name must be 1-200 characters"))]
+    pub name: Option<String>,
+    #[validate(length(max = 2000, message = "Description too long (max 2000 chars)"))]
+    pub description: Option<String>,
+    #[validate(nested)]
+    pub criteria: Option<CohortCriteria>,
+}
+
+/// A patient of a cohort
+#[derive(Debug, Clone, Serialize)]
+pub struct CohortMember {
+    pub patient_id: Uuid,
+    pub medical_record_number: String,
+    pub first_name: String,
+    pub last_name: String,
+    pub age: Option<u32>,
+    pub last_visit_date: Option<NaiveDate>,
+}
+
+/// Size of a cohort and its first patients
+#[derive(Debug, Clone, Serialize)]
+pub struct CohortPreview {
+    /// Patients in the cohort
+    pub count: usize,
+    /// The first COHORT_PREVIEW_LIMIT patients, by medical record number
+    pub patients: Vec<CohortMember>,
+    /// Patients skipped because their data could not be decrypted
+    pub unreadable_records: usize,
+}
+
+/// Request body for POST /api/v1/cohorts/:id/documents
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct CohortDocumentsRequest {
+    pub template_id: Uuid,
+    #[validate(length(
+        min = 1,
+        max = 255,
+        message = "Document title prefix must be 1-255 characters"
+    ))]
+    pub title_prefix: String,
+    /// Common additional data for all documents
+    pub common_data: Option<serde_json::Value>,
+}
+
+/// Kind of notification a campaign sends
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum CampaignType {
+    Custom,
+    /// Only sent to patients with an active MARKETING consent
+    Marketing,
+}
+
+impl CampaignType {
+    /// Notification type of the queued notifications
+    pub fn notification_type(&self) -> NotificationType {
+        match self {
+            CampaignType::Custom => NotificationType::Custom,
+            CampaignType::Marketing => NotificationType::Marketing,
+        }
+    }
+}
+
+/// Request body for POST /api/v1/cohorts/:id/notifications
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct CohortNotificationRequest {
+    pub notification_type: CampaignType,
+    #[validate(length(min = 1, max = 255, message = "Subject must be 1-255 characters"))]
+    pub subject: String,
+    #[validate(length(
+        min = 1,
+        max = 10000,
+        message = "Message body must be 1-10000 characters"
+    ))]
+    pub message_body: String,
+    /// When to send (defaults to now)
+    pub scheduled_for: Option<DateTime<Utc>>,
+}
+
+/// A cohort patient a campaign did not reach
+#[derive(Debug, Clone, Serialize)]
+pub struct CohortCampaignSkip {
+    pub patient_id: Uuid,
+    pub reason: String,
+}
+
+/// Outcome of a notification campaign
+#[derive(Debug, Clone, Serialize)]
+pub struct CohortNotificationResult {
+    /// Patients in the cohort
+    pub total: usize,
+    /// Notifications queued
+    pub queued: usize,
+    /// Patients without an email address, with email disabled, without
+    /// MARKETING consent or whose notification could not be queued
+    pub skipped: Vec<CohortCampaignSkip>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_criteria_validation() {
+        assert!(CohortCriteria::default().validate().is_ok());
+
+        let criteria = CohortCriteria {
+            min_age: Some(65),
+            max_age: Some(80),
+            diagnosis_codes: vec!["E11".to_string(), "i10".to_string()],
+            medications: vec!["metformin".to_string()],
+            last_visit_before: NaiveDate::from_ymd_opt(2025, 1, 1),
+        };
+        assert!(criteria.validate().is_ok());
+
+        let inverted = CohortCriteria {
+            min_age: Some(80),
+            max_age: Some(65),
+            ..Default::default()
+        };
+        assert!(inverted.validate().is_err());
+
+        let wildcard = CohortCriteria {
+            diagnosis_codes: vec!["E1%".to_string()],
+            ..Default::default()
+        };
+        assert!(wildcard.validate().is_err());
+
+        let short_name = CohortCriteria {
+            medications: vec!["a".to_string()],
+            ..Default::default()
+        };
+        assert!(short_name.validate().is_err());
+    }
+
+    #[test]
+    fn test_criteria_matching() {
+        let criteria = CohortCriteria {
+            min_age: Some(18),
+            max_age: Some(65),
+            diagnosis_codes: vec![" e11 ".to_string()],
+            medications: vec!["Metformin".to_string()],
+            last_visit_before: None,
+        };
+
+        assert_eq!(criteria.diagnosis_patterns(), vec!["E11%".to_string()]);
+        assert!(criteria.matches_age(18));
+        assert!(criteria.matches_age(65));
+        assert!(!criteria.matches_age(66));
+        assert!(criteria.matches_medication("Glucophage", Some("metformin hydrochloride")));
+        assert!(!criteria.matches_medication("Lasix", Some("furosemide")));
+    }
+
+    #[test]
+    fn test_campaign_type() {
+        let campaign: CampaignType = serde_json::from_str("\"MARKETING\"").unwrap();
+        assert_eq!(campaign, CampaignType::Marketing);
+        assert_eq!(campaign.notification_type(), NotificationType::Marketing);
+        assert!(serde_json::from_str::<CampaignType>("\"APPOINTMENT_REMINDER\"").is_err());
+    }
+}
@@ -0,0 +1,291 @@
+/*!
+ * Patient Communication Model
+ *
+ * Per-patient communication log: notifications sent to the patient and
+ * documents delivered are recorded automatically, phone calls, letters and
+ * other contacts by staff. Entries are not edited; staff annotate them.
+ * Manual notes and annotations are encrypted.
+ */
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::utils::encryption::EncryptionKey;
+
+/// Default page size of the communication log
+pub const DEFAULT_COMMUNICATION_LIMIT: i64 = 50;
+
+/// Largest page size of the communication log
+pub const MAX_COMMUNICATION_LIMIT: i64 = 200;
+
+/// How an entry got into the log
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum CommunicationSource {
+    /// A notification sent to the patient
+    Notification,
+    /// A generated document marked delivered
+    Document,
+    /// Recorded by staff
+    Manual,
+}
+
+impl CommunicationSource {
+    /// Convert to database string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CommunicationSource::Notification => "NOTIFICATION",
+            CommunicationSource::Document => "DOCUMENT",
+            CommunicationSource::Manual => "MANUAL",
+        }
+    }
+}
+
+/// Medium of a communication
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum CommunicationChannel {
+    Email,
+    Sms,
+    Whatsapp,
+    Phone,
+    Letter,
+    InPerson,
+    Other,
+}
+
+impl CommunicationChannel {
+    /// Convert to database string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CommunicationChannel::Email => "EMAIL",
+            CommunicationChannel::Sms => "SMS",
+            CommunicationChannel::Whatsapp => "WHATSAPP",
+            CommunicationChannel::Phone => "PHONE",
+            CommunicationChannel::Letter => "LETTER",
+            CommunicationChannel::InPerson => "IN_PERSON",
+            CommunicationChannel::Other => "OTHER",
+        }
+    }
+}
+
+/// Whether the practice contacted the patient or the other way round
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum CommunicationDirection {
+    #[default]
+    Outbound,
+    Inbound,
+}
+
+impl CommunicationDirection {
+    /// Convert to database string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CommunicationDirection::Outbound => "OUTBOUND",
+            CommunicationDirection::Inbound => "INBOUND",
+        }
+    }
+}
+
+/// Communication log entry row (encrypted)
+#[derive(Debug, Clone, FromRow)]
+pub struct PatientCommunication {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub source: String,
+    pub channel: String,
+    pub direction: String,
+    pub notification_id: Option<Uuid>,
+    pub document_id: Option<Uuid>,
+    pub recipient: Option<String>,
+    pub subject: Option<String>,
+    pub notes: Option<String>, // 🔒 Encrypted
+    pub occurred_at: DateTime<Utc>,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+}
+
+impl PatientCommunication {
+    /// Decrypt into the API representation
+    pub fn decrypt(
+        &self,
+        key: &EncryptionKey,
+        annotations: Vec<CommunicationAnnotationResponse>,
+    ) -> Result<PatientCommunicationResponse> {
+        Ok(PatientCommunicationResponse {
+            id: self.id,
+            patient_id: self.patient_id,
+            source: self.source.clone(),
+            channel: self.channel.clone(),
+            direction: self.direction.clone(),
+            notification_id: self.notification_id,
+            document_id: self.document_id,
+            recipient: self.recipient.clone(),
+            subject: self.subject.clone(),
+            notes: key
+                .decrypt_optional(&self.notes)
+                .context("Failed to decrypt communication notes")?,
+            occurred_at: self.occurred_at,
+            annotations,
+            created_by: self.created_by,
+            created_at: self.created_at,
+        })
+    }
+}
+
+/// Annotation row (encrypted)
+#[derive(Debug, Clone, FromRow)]
+pub struct CommunicationAnnotation {
+    pub id: Uuid,
+    pub communication_id: Uuid,
+    pub note: String, // 🔒 Encrypted
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+}
+
+impl CommunicationAnnotation {
+    /// Decrypt into the API representation
+    pub fn decrypt(&self, key: &EncryptionKey) -> Result<CommunicationAnnotationResponse> {
+        Ok(CommunicationAnnotationResponse {
+            id: self.id,
+            communication_id: self.communication_id,
+            note: key
+                .decrypt(&self.note)
+                .context("Failed to decrypt communication annotation")?,
+            created_by: self.created_by,
+            created_at: self.created_at,
+        })
+    }
+}
+
+/// Annotation (API output, decrypted)
+#[derive(Debug, Clone, Serialize)]
+pub struct CommunicationAnnotationResponse {
+    pub id: Uuid,
+    pub communication_id: Uuid,
+    pub note: String,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Communication log entry (API output, decrypted)
+#[derive(Debug, Clone, Serialize)]
+pub struct PatientCommunicationResponse {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub source: String,
+    pub channel: String,
+    pub direction: String,
+    pub notification_id: Option<Uuid>,
+    pub document_id: Option<Uuid>,
+    pub recipient: Option<String>,
+    pub subject: Option<String>,
+    pub notes: Option<String>,
+    pub occurred_at: DateTime<Utc>,
+    /// Oldest first
+    pub annotations: Vec<CommunicationAnnotationResponse>,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Page of a patient's communication log, most recent first
+#[derive(Debug, Clone, Serialize)]
+pub struct ListPatientCommunicationsResponse {
+    pub communications: Vec<PatientCommunicationResponse>,
+    pub total: i64,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+/// Request body for POST /api/v1/patients/:id/communications
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct CreatePatientCommunicationRequest {
+    pub channel: CommunicationChannel,
+    #[serde(default)]
+    pub direction: CommunicationDirection,
+    /// When the contact took place; now when absent
+    pub occurred_at: Option<DateTime<Utc>>,
+    #[validate(length(max = 255, message = "Recipient too long (max 255 chars)"))]
+    pub recipient: Option<String>,
+    #[validate(length(max = 255, message = "Subject too long (max 255 chars)"))]
+    pub subject: Option<String>,
+    #[validate(length(min = 1, max = 5000, message = "Notes must be 1-5000 characters"))]
+    pub notes: String,
+}
+
+/// Request body for POST /api/v1/patients/:id/communications/:communication_id/annotations
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct CreateCommunicationAnnotationRequest {
+    #[validate(length(min = 1, max = 2000, message = "Note must be 1-2000 characters"))]
+    pub note: String,
+}
+
+/// Query parameters for GET /api/v1/patients/:id/communications
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ListPatientCommunicationsQuery {
+    pub source: Option<CommunicationSource>,
+    pub channel: Option<CommunicationChannel>,
+    /// Entries from this time on
+    pub from: Option<DateTime<Utc>>,
+    /// Entries before this time
+    pub to: Option<DateTime<Utc>>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+impl ListPatientCommunicationsQuery {
+    /// Page size, clamped to 1-MAX_COMMUNICATION_LIMIT
+    pub fn limit(&self) -> i64 {
+        self.limit
+            .unwrap_or(DEFAULT_COMMUNICATION_LIMIT)
+            .clamp(1, MAX_COMMUNICATION_LIMIT)
+    }
+
+    /// Offset, never negative
+    pub fn offset(&self) -> i64 {
+        self.offset.unwrap_or(0).max(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_communication_request() {
+        let request: CreatePatientCommunicationRequest = serde_json::from_str(
+            r#"{"channel": "PHONE", "notes": "Called about lab results, left a message"}"#,
+        )
+        .unwrap();
+        assert!(request.validate().is_ok());
+        assert_eq!(request.channel.as_str(), "PHONE");
+        assert_eq!(request.direction, CommunicationDirection::Outbound);
+
+        let inbound: CreatePatientCommunicationRequest = serde_json::from_str(
+            r#"{"channel": "IN_PERSON", "direction": "INBOUND", "notes": ""}"#,
+        )
+        .unwrap();
+        assert_eq!(inbound.direction.as_str(), "INBOUND");
+        assert!(inbound.validate().is_err());
+    }
+
+    #[test]
+    fn test_list_query_paging() {
+        let query = ListPatientCommunicationsQuery::default();
+        assert_eq!(query.limit(), DEFAULT_COMMUNICATION_LIMIT);
+        assert_eq!(query.offset(), 0);
+
+        let query = ListPatientCommunicationsQuery {
+            limit: Some(10_000),
+            offset: Some(-5),
+            ..Default::default()
+        };
+        assert_eq!(query.limit(), MAX_COMMUNICATION_LIMIT);
+        assert_eq!(query.offset(), 0);
+    }
+}
@@ -0,0 +1,515 @@
+/*!
+ * Patient Consent Model
+ *
+ * Consent texts are versioned: each consent type has a sequence of immutable
+ * texts, and a patient consent refers to the exact version that was signed.
+ * A consent stays active until it is withdrawn, superseded by a newer consent
+ * of the same type, or reaches its expiry.
+ */
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
+
+/// Kind of consent a patient can give
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ConsentType {
+    /// Processing of health data for care (GDPR art. 9)
+    DataProcessing,
+    /// Newsletters and promotional messages
+    Marketing,
+    /// Remote consultations
+    Telemedicine,
+    /// Sharing data with third parties (labs, insurers, other providers)
+    ThirdPartySharing,
+}
+
+impl ConsentType {
+    /// Convert to database string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConsentType::DataProcessing => "DATA_PROCESSING",
+            ConsentType::Marketing => "MARKETING",
+            ConsentType::Telemedicine => "TELEMEDICINE",
+            ConsentType::ThirdPartySharing => "THIRD_PARTY_SHARING",
+        }
+    }
+
+    /// Parse from database string
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "DATA_PROCESSING" => Some(ConsentType::DataProcessing),
+            "MARKETING" => Some(ConsentType::Marketing),
+            "TELEMEDICINE" => Some(ConsentType::Telemedicine),
+            "THIRD_PARTY_SHARING" => Some(ConsentType::ThirdPartySharing),
+            _ => None,
+        }
+    }
+}
+
+/// Status of a patient consent
+///
+/// EXPIRED is never stored: a GRANTED consent past its `expires_at` is
+/// reported as EXPIRED.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ConsentStatus {
+    Granted,
+    Withdrawn,
+    Superseded,
+    Expired,
+}
+
+impl ConsentStatus {
+    /// Convert to database string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConsentStatus::Granted => "GRANTED",
+            ConsentStatus::Withdrawn => "WITHDRAWN",
+            ConsentStatus::Superseded => "SUPERSEDED",
+            ConsentStatus::Expired => "EXPIRED",
+        }
+    }
+
+    /// Parse from database string
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "GRANTED" => Some(ConsentStatus::Granted),
+            "WITHDRAWN" => Some(ConsentStatus::Withdrawn),
+            "SUPERSEDED" => Some(ConsentStatus::Superseded),
+            "EXPIRED" => Some(ConsentStatus::Expired),
+            _ => None,
+        }
+    }
+}
+
+/// How the patient signed a consent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SignatureMethod {
+    /// Signature captured on a tablet or signature pad (image required)
+    Handwritten,
+    /// Accepted electronically (e.g. checkbox in a form)
+    Electronic,
+    /// Given verbally and recorded by staff
+    Verbal,
+    /// Signed on paper, kept in the paper record
+    Paper,
+}
+
+impl SignatureMethod {
+    /// Convert to database string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SignatureMethod::Handwritten => "HANDWRITTEN",
+            SignatureMethod::Electronic => "ELECTRONIC",
+            SignatureMethod::Verbal => "VERBAL",
+            SignatureMethod::Paper => "PAPER",
+        }
+    }
+
+    /// Parse from database string
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "HANDWRITTEN" => Some(SignatureMethod::Handwritten),
+            "ELECTRONIC" => Some(SignatureMethod::Electronic),
+            "VERBAL" => Some(SignatureMethod::Verbal),
+            "PAPER" => Some(SignatureMethod::Paper),
+            _ => None,
+        }
+    }
+}
+
+// ============================================================================
+// CONSENT TEXTS
+// ============================================================================
+
+/// Consent text row
+#[derive(Debug, Clone, FromRow)]
+pub struct ConsentText {
+    pub id: Uuid,
+    pub consent_type: String,
+    pub version: i32,
+    pub title: String,
+    pub body: String,
+    pub validity_days: Option<i32>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ConsentText {
+    /// Default expiry of a consent to this text granted at `granted_at`
+    pub fn expiry_from(&self, granted_at: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.validity_days
+            .map(|days| granted_at + Duration::days(days as i64))
+    }
+}
+
+/// Consent text (API output)
+#[derive(Debug, Clone, Serialize)]
+pub struct ConsentTextResponse {
+    pub id: Uuid,
+    pub consent_type: ConsentType,
+    pub version: i32,
+    pub title: String,
+    pub body: String,
+    pub validity_days: Option<i32>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl TryFrom<ConsentText> for ConsentTextResponse {
+    type Error = String;
+
+    fn try_from(text: ConsentText) -> Result<Self, Self::Error> {
+        let consent_type = ConsentType::from_str(&text.consent_type)
+            .ok_or_else(|| format!("Unknown consent type: {}", text.consent_type))?;
+
+        Ok(Self {
+            id: text.id,
+            consent_type,
+            version: text.version,
+            title: text.title,
+            body: text.body,
+            validity_days: text.validity_days,
+            created_by: text.created_by,
+            created_at: text.created_at,
+        })
+    }
+}
+
+/// Request body for POST /api/v1/consent-texts
+///
+/// The version is assigned by the server (previous version + 1).
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct CreateConsentTextRequest {
+    pub consent_type: ConsentType,
+    #[validate(length(min = 1, max = 255, message = "Title must be 1-255 characters"))]
+    pub title: String,
+    #[validate(length(min = 1, max = 50000, message = "Body must be 1-50000 characters"))]
+    pub body: String,
+    #[validate(range(min = 1, max = 36500, message = "Validity must be 1-36500 days"))]
+    pub validity_days: Option<i32>,
+}
+
+/// Query parameters for GET /api/v1/consent-texts
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ListConsentTextsQuery {
+    pub consent_type: Option<ConsentType>,
+    /// Only the latest version of each type
+    #[serde(default)]
+    pub latest_only: bool,
+}
+
+// ============================================================================
+// PATIENT CONSENTS
+// ============================================================================
+
+/// Patient consent row (signature_data is encrypted)
+#[derive(Debug, Clone, FromRow)]
+pub struct PatientConsent {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub consent_type: String,
+    pub consent_text_id: Uuid,
+    pub consent_text_version: i32,
+    pub status: String,
+    pub granted_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub signature_method: String,
+    pub signature_data: Option<String>,
+    pub signer_name: Option<String>,
+    pub signer_relationship: Option<String>,
+    pub withdrawn_at: Option<DateTime<Utc>>,
+    pub withdrawn_by: Option<Uuid>,
+    pub withdrawal_reason: Option<String>,
+    pub recorded_by: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl PatientConsent {
+    /// Status as of `now`, with GRANTED consents past expiry reported as EXPIRED
+    pub fn effective_status(&self, now: DateTime<Utc>) -> Option<ConsentStatus> {
+        let status = ConsentStatus::from_str(&self.status)?;
+        match (status, self.expires_at) {
+            (ConsentStatus::Granted, Some(expires_at)) if expires_at <= now => {
+                Some(ConsentStatus::Expired)
+            }
+            _ => Some(status),
+        }
+    }
+
+    /// Whether the consent currently allows what it covers
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.effective_status(now) == Some(ConsentStatus::Granted)
+    }
+}
+
+/// Patient consent (API output)
+///
+/// `signature_data` is only included when a single consent is fetched.
+#[derive(Debug, Clone, Serialize)]
+pub struct PatientConsentResponse {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub consent_type: ConsentType,
+    pub consent_text_id: Uuid,
+    pub consent_text_version: i32,
+    pub status: ConsentStatus,
+    pub granted_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub signature_method: SignatureMethod,
+    pub has_signature: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature_data: Option<String>,
+    pub signer_name: Option<String>,
+    pub signer_relationship: Option<String>,
+    pub withdrawn_at: Option<DateTime<Utc>>,
+    pub withdrawn_by: Option<Uuid>,
+    pub withdrawal_reason: Option<String>,
+    pub recorded_by: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl PatientConsentResponse {
+    /// Build the response without the signature image
+    pub fn from_consent(consent: PatientConsent, now: DateTime<Utc>) -> Result<Self, String> {
+        let consent_type = ConsentType::from_str(&consent.consent_type)
+            .ok_or_else(|| format!("Unknown consent type: {}", consent.consent_type))?;
+        let status = consent
+            .effective_status(now)
+            .ok_or_else(|| format!("Unknown consent status: {}", consent.status))?;
+        let signature_method = SignatureMethod::from_str(&consent.signature_method)
+            .ok_or_else(|| format!("Unknown signature method: {}", consent.signature_method))?;
+
+        Ok(Self {
+            id: consent.id,
+            patient_id: consent.patient_id,
+            consent_type,
+            consent_text_id: consent.consent_text_id,
+            consent_text_version: consent.consent_text_version,
+            status,
+            granted_at: consent.granted_at,
+            expires_at: consent.expires_at,
+            signature_method,
+            has_signature: consent.signature_data.is_some(),
+            signature_data: None,
+            signer_name: consent.signer_name,
+            signer_relationship: consent.signer_relationship,
+            withdrawn_at: consent.withdrawn_at,
+            withdrawn_by: consent.withdrawn_by,
+            withdrawal_reason: consent.withdrawal_reason,
+            recorded_by: consent.recorded_by,
+            created_at: consent.created_at,
+            updated_at: consent.updated_at,
+        })
+    }
+}
+
+/// Single patient consent with its text and signature
+#[derive(Debug, Clone, Serialize)]
+pub struct PatientConsentDetailResponse {
+    #[serde(flatten)]
+    pub consent: PatientConsentResponse,
+    pub consent_text: ConsentTextResponse,
+}
+
+/// Request body for POST /api/v1/patients/:id/consents
+#[derive(Debug, Clone, Deserialize, Validate)]
+#[validate(schema(function = "validate_record_consent"))]
+pub struct RecordConsentRequest {
+    pub consent_type: ConsentType,
+    /// Text version being consented to (defaults to the latest for the type)
+    pub consent_text_id: Option<Uuid>,
+    pub signature_method: SignatureMethod,
+    /// Signature image as a data URL (required for HANDWRITTEN, ~500 KB max)
+    #[validate(length(max = 700000, message = "Signature image is too large"))]
+    pub signature_data: Option<String>,
+    /// Who signed, when not the patient
+    #[validate(length(max = 255, message = "Signer name too long"))]
+    pub signer_name: Option<String>,
+    #[validate(length(max = 50, message = "Signer relationship too long"))]
+    pub signer_relationship: Option<String>,
+    /// Overrides the text's default validity
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+fn validate_record_consent(req: &RecordConsentRequest) -> Result<(), ValidationError> {
+    let has_signature = req
+        .signature_data
+        .as_deref()
+        .is_some_and(|data| !data.trim().is_empty());
+
+    if req.signature_method == SignatureMethod::Handwritten && !has_signature {
+        let mut error = ValidationError::new("signature_required");
+        error.message = Some("A handwritten consent requires signature_data".into());
+        return Err(error);
+    }
+
+    if let Some(data) = req.signature_data.as_deref() {
+        if !data.starts_with("data:image/") {
+            let mut error = ValidationError::new("invalid_signature");
+            error.message = Some("signature_data must be an image data URL".into());
+            return Err(error);
+        }
+    }
+
+    if req.signer_relationship.is_some() && req.signer_name.is_none() {
+        let mut error = ValidationError::new("signer_name_required");
+        error.message = Some("signer_relationship requires signer_name".into());
+        return Err(error);
+    }
+
+    Ok(())
+}
+
+/// Request body for POST /api/v1/patients/:id/consents/:consent_id/withdraw
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct WithdrawConsentRequest {
+    #[validate(length(max = 2000, message = "Reason must be at most 2000 characters"))]
+    pub reason: Option<String>,
+}
+
+/// Query parameters for GET /api/v1/patients/:id/consents
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ListPatientConsentsQuery {
+    pub consent_type: Option<ConsentType>,
+    /// Only consents that are currently active
+    #[serde(default)]
+    pub active_only: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn consent(status: ConsentStatus, expires_at: Option<DateTime<Utc>>) -> PatientConsent {
+        let now = Utc::now();
+        PatientConsent {
+            id: Uuid::new_v4(),
+            patient_id: Uuid::new_v4(),
+            consent_type: ConsentType::Marketing.as_str().to_string(),
+            consent_text_id: Uuid::new_v4(),
+            consent_text_version: 1,
+            status: status.as_str().to_string(),
+            granted_at: now - Duration::days(10),
+            expires_at,
+            signature_method: SignatureMethod::Electronic.as_str().to_string(),
+            signature_data: None,
+            signer_name: None,
+            signer_relationship: None,
+            withdrawn_at: None,
+            withdrawn_by: None,
+            withdrawal_reason: None,
+            recorded_by: Uuid::new_v4(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    fn record_request(method: SignatureMethod, signature: Option<&str>) -> RecordConsentRequest {
+        RecordConsentRequest {
+            consent_type: ConsentType::Marketing,
+            consent_text_id: None,
+            signature_method: method,
+            signature_data: signature.map(str::to_string),
+            signer_name: None,
+            signer_relationship: None,
+            expires_at: None,
+        }
+    }
+
+    #[test]
+    fn test_consent_enum_conversion() {
+        for consent_type in [
+            ConsentType::DataProcessing,
+            ConsentType::Marketing,
+            ConsentType::Telemedicine,
+            ConsentType::ThirdPartySharing,
+        ] {
+            assert_eq!(
+                ConsentType::from_str(consent_type.as_str()),
+                Some(consent_type)
+            );
+        }
+        for method in [
+            SignatureMethod::Handwritten,
+            SignatureMethod::Electronic,
+            SignatureMethod::Verbal,
+            SignatureMethod::Paper,
+        ] {
+            assert_eq!(SignatureMethod::from_str(method.as_str()), Some(method));
+        }
+        assert_eq!(ConsentType::from_str("NEWSLETTER"), None);
+    }
+
+    #[test]
+    fn test_consent_effective_status() {
+        let now = Utc::now();
+
+        assert!(consent(ConsentStatus::Granted, None).is_active(now));
+        assert!(consent(ConsentStatus::Granted, Some(now + Duration::days(1))).is_active(now));
+
+        let expired = consent(ConsentStatus::Granted, Some(now - Duration::seconds(1)));
+        assert_eq!(expired.effective_status(now), Some(ConsentStatus::Expired));
+        assert!(!expired.is_active(now));
+
+        assert!(!consent(ConsentStatus::Withdrawn, None).is_active(now));
+        assert!(!consent(ConsentStatus::Superseded, None).is_active(now));
+    }
+
+    #[test]
+    fn test_consent_response_hides_signature() {
+        let mut row = consent(ConsentStatus::Granted, None);
+        row.signature_data = Some("ciphertext".to_string());
+
+        let response = PatientConsentResponse::from_consent(row, Utc::now()).unwrap();
+        assert!(response.has_signature);
+        assert!(response.signature_data.is_none());
+    }
+
+    #[test]
+    fn test_consent_text_expiry() {
+        let now = Utc::now();
+        let mut text = ConsentText {
+            id: Uuid::new_v4(),
+            consent_type: ConsentType::Marketing.as_str().to_string(),
+            version: 2,
+            title: "Marketing".to_string(),
+            body: "I agree to receive newsletters".to_string(),
+            validity_days: Some(365),
+            created_by: None,
+            created_at: now,
+        };
+        assert_eq!(text.expiry_from(now), Some(now + Duration::days(365)));
+
+        text.validity_days = None;
+        assert_eq!(text.expiry_from(now), None);
+    }
+
+    #[test]
+    fn test_record_consent_signature_validation() {
+        assert!(record_request(SignatureMethod::Handwritten, None)
+            .validate()
+            .is_err());
+        assert!(
+            record_request(SignatureMethod::Handwritten, Some("not-an-image"))
+                .validate()
+                .is_err()
+        );
+        assert!(record_request(
+            SignatureMethod::Handwritten,
+            Some("data:image/png;base64,iVBORw0")
+        )
+        .validate()
+        .is_ok());
+        assert!(record_request(SignatureMethod::Verbal, None)
+            .validate()
+            .is_ok());
+    }
+}
@@ -0,0 +1,232 @@
+/*!
+ * Patient Erasure Model
+ *
+ * GDPR right to erasure with four-eyes review: an ADMIN files a request, a
+ * different ADMIN approves (anonymizing the patient) or rejects it.
+ * Anonymization is irreversible; the patient row is kept as a tombstone so
+ * legally retained clinical records (visits, diagnoses, prescriptions) and
+ * the audit trail stay consistent.
+ */
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+/// First name written over an erased patient's name
+pub const ANONYMIZED_FIRST_NAME: &str = "Anonymized";
+
+/// Last name written over an erased patient's name
+pub const ANONYMIZED_LAST_NAME: &str = "Patient";
+
+/// Message body written over an erased patient's notifications and messages
+pub const ERASED_MESSAGE_BODY: &str = "[erased]";
+
+/// Status of an erasure request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErasureRequestStatus {
+    /// Awaiting review by a second admin
+    Pending,
+    /// Approved and executed
+    Completed,
+    Rejected,
+}
+
+impl ErasureRequestStatus {
+    /// Convert to database string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErasureRequestStatus::Pending => "PENDING",
+            ErasureRequestStatus::Completed => "COMPLETED",
+            ErasureRequestStatus::Rejected => "REJECTED",
+        }
+    }
+
+    /// Parse from database string
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "PENDING" => Some(ErasureRequestStatus::Pending),
+            "COMPLETED" => Some(ErasureRequestStatus::Completed),
+            "REJECTED" => Some(ErasureRequestStatus::Rejected),
+            _ => None,
+        }
+    }
+}
+
+/// What an approved erasure changed, per table
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErasureSummary {
+    pub insurance_deleted: u64,
+    pub notification_preferences_deleted: u64,
+    pub notifications_cancelled: u64,
+    pub notifications_scrubbed: u64,
+    pub appointments_cancelled: u64,
+    pub documents_deleted: u64,
+    pub exports_purged: u64,
+    /// Photo and thumbnail files (absent from summaries recorded before photos)
+    #[serde(default)]
+    pub photos_deleted: u64,
+    /// Communication log entries scrubbed (absent from summaries recorded
+    /// before the log)
+    #[serde(default)]
+    pub communications_scrubbed: u64,
+    /// Messages scrubbed (absent from summaries recorded before messaging)
+    #[serde(default)]
+    pub messages_scrubbed: u64,
+    /// Survey comments scrubbed (absent from summaries recorded before
+    /// surveys)
+    #[serde(default)]
+    pub survey_comments_scrubbed: u64,
+}
+
+/// Erasure request row
+#[derive(Debug, Clone, FromRow)]
+pub struct PatientErasureRequest {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub reason: String,
+    pub status: String,
+    pub requested_by: Uuid,
+    pub requested_at: DateTime<Utc>,
+    pub reviewed_by: Option<Uuid>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+    pub review_notes: Option<String>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub summary: Option<serde_json::Value>,
+}
+
+/// Erasure request (API output)
+#[derive(Debug, Clone, Serialize)]
+pub struct PatientErasureRequestResponse {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub reason: String,
+    pub status: ErasureRequestStatus,
+    pub requested_by: Uuid,
+    pub requested_at: DateTime<Utc>,
+    pub reviewed_by: Option<Uuid>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+    pub review_notes: Option<String>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub summary: Option<ErasureSummary>,
+}
+
+impl From<PatientErasureRequest> for PatientErasureRequestResponse {
+    fn from(request: PatientErasureRequest) -> Self {
+        Self {
+            id: request.id,
+            patient_id: request.patient_id,
+            reason: request.reason,
+            status: ErasureRequestStatus::from_str(&request.status)
+                .unwrap_or(ErasureRequestStatus::Pending),
+            requested_by: request.requested_by,
+            requested_at: request.requested_at,
+            reviewed_by: request.reviewed_by,
+            reviewed_at: request.reviewed_at,
+            review_notes: request.review_notes,
+            completed_at: request.completed_at,
+            summary: request
+                .summary
+                .and_then(|summary| serde_json::from_value(summary).ok()),
+        }
+    }
+}
+
+/// Request body for POST /api/v1/patients/:id/erasure-requests
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct CreateErasureRequest {
+    /// Why the data is erased (e.g. the patient's written request reference)
+    #[validate(length(min = 10, max = 2000, message = "Reason must be 10-2000 characters"))]
+    pub reason: String,
+}
+
+/// Request body for POST /api/v1/erasure-requests/:id/approve
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct ApproveErasureRequest {
+    /// Must match the patient's medical record number, as a guard against
+    /// approving the wrong request
+    #[validate(length(min = 1, max = 50))]
+    pub confirm_medical_record_number: String,
+    #[validate(length(max = 2000))]
+    pub notes: Option<String>,
+}
+
+/// Request body for POST /api/v1/erasure-requests/:id/reject
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct RejectErasureRequest {
+    #[validate(length(min = 1, max = 2000, message = "Notes must be 1-2000 characters"))]
+    pub notes: String,
+}
+
+/// Query parameters for listing erasure requests
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ListErasureRequestsQuery {
+    pub status: Option<ErasureRequestStatus>,
+    pub patient_id: Option<Uuid>,
+}
+
+/// Date of birth kept after erasure: January 1st of the birth year
+///
+/// Keeps the age band needed to interpret retained clinical records without
+/// the exact date. Returns None if `date_of_birth` is not a `YYYY-MM-DD` date.
+pub fn anonymized_date_of_birth(date_of_birth: &str) -> Option<String> {
+    let date = chrono::NaiveDate::parse_from_str(date_of_birth, "%Y-%m-%d").ok()?;
+    Some(format!("{:04}-01-01", chrono::Datelike::year(&date)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_erasure_request_status_conversion() {
+        for status in [
+            ErasureRequestStatus::Pending,
+            ErasureRequestStatus::Completed,
+            ErasureRequestStatus::Rejected,
+        ] {
+            assert_eq!(
+                ErasureRequestStatus::from_str(status.as_str()),
+                Some(status)
+            );
+        }
+        assert_eq!(ErasureRequestStatus::from_str("APPROVED"), None);
+    }
+
+    #[test]
+    fn test_anonymized_date_of_birth() {
+        assert_eq!(
+            anonymized_date_of_birth("1987-06-23"),
+            Some("1987-01-01".to_string())
+        );
+        assert_eq!(anonymized_date_of_birth("23/06/1987"), None);
+    }
+
+    #[test]
+    fn test_erasure_summary_round_trip() {
+        let summary = ErasureSummary {
+            insurance_deleted: 1,
+            notifications_scrubbed: 4,
+            ..Default::default()
+        };
+        let request = PatientErasureRequest {
+            id: Uuid::new_v4(),
+            patient_id: Uuid::new_v4(),
+            reason: "Written request ref. 2026/14".to_string(),
+            status: "COMPLETED".to_string(),
+            requested_by: Uuid::new_v4(),
+            requested_at: Utc::now(),
+            reviewed_by: Some(Uuid::new_v4()),
+            reviewed_at: Some(Utc::now()),
+            review_notes: None,
+            completed_at: Some(Utc::now()),
+            summary: Some(serde_json::to_value(&summary).unwrap()),
+        };
+
+        let response = PatientErasureRequestResponse::from(request);
+        assert_eq!(response.status, ErasureRequestStatus::Completed);
+        assert_eq!(response.summary, Some(summary));
+    }
+}
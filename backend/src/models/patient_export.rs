@@ -0,0 +1,210 @@
+/*!
+ * Patient Data Export Model
+ *
+ * GDPR subject access export: a machine-readable bundle of everything stored
+ * about a patient (demographics, insurance, visits, diagnoses, prescriptions,
+ * appointments, documents, notifications and the record access trail),
+ * produced in the background as an AES-256 encrypted zip.
+ *
+ * The passphrase is supplied with the request and only used while the bundle
+ * is written; it is never stored or logged.
+ */
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+/// Version of the bundle layout, recorded in `manifest.json`
+///
+/// 2: visit attachments (`visit_attachments.json` and `visit_attachments/`)
+pub const PATIENT_EXPORT_FORMAT_VERSION: u32 = 2;
+
+/// Default number of days a completed bundle stays downloadable
+pub const DEFAULT_PATIENT_EXPORT_RETENTION_DAYS: i64 = 7;
+
+/// Status of a patient data export
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum PatientExportStatus {
+    Processing,
+    Completed,
+    Failed,
+    /// Bundle deleted from storage after the retention period
+    Expired,
+}
+
+impl PatientExportStatus {
+    /// Convert to database string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PatientExportStatus::Processing => "PROCESSING",
+            PatientExportStatus::Completed => "COMPLETED",
+            PatientExportStatus::Failed => "FAILED",
+            PatientExportStatus::Expired => "EXPIRED",
+        }
+    }
+
+    /// Parse from database string
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "PROCESSING" => Some(PatientExportStatus::Processing),
+            "COMPLETED" => Some(PatientExportStatus::Completed),
+            "FAILED" => Some(PatientExportStatus::Failed),
+            "EXPIRED" => Some(PatientExportStatus::Expired),
+            _ => None,
+        }
+    }
+}
+
+/// Patient data export row
+#[derive(Debug, Clone, FromRow)]
+pub struct PatientExport {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub requested_by: Uuid,
+    pub status: String,
+    pub file_path: Option<String>,
+    pub file_size_bytes: Option<i64>,
+    pub file_hash: Option<String>,
+    pub error_message: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub download_count: i32,
+    pub last_downloaded_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+impl PatientExport {
+    /// Whether the bundle can be downloaded at `now`
+    pub fn is_downloadable(&self, now: DateTime<Utc>) -> bool {
+        self.status == PatientExportStatus::Completed.as_str()
+            && self.file_path.is_some()
+            && self.expires_at.is_some_and(|expires_at| expires_at > now)
+    }
+
+    /// Filename offered to the client
+    pub fn download_filename(&self) -> String {
+        format!(
+            "patient_export_{}_{}.zip",
+            self.patient_id,
+            self.created_at.format("%Y%m%d_%H%M%S")
+        )
+    }
+}
+
+/// Request body for POST /api/v1/patients/:id/export
+#[derive(Clone, Deserialize, Validate)]
+pub struct CreatePatientExportRequest {
+    /// Passphrase protecting the zip, handed to the patient separately
+    #[validate(length(min = 12, max = 128, message = "Passphrase must be 12-128 characters"))]
+    pub passphrase: String,
+}
+
+impl std::fmt::Debug for CreatePatientExportRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CreatePatientExportRequest")
+            .field("passphrase", &"[REDACTED]")
+            .finish()
+    }
+}
+
+/// Patient data export (API output)
+#[derive(Debug, Clone, Serialize)]
+pub struct PatientExportResponse {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub requested_by: Uuid,
+    pub status: PatientExportStatus,
+    pub file_size_bytes: Option<i64>,
+    /// SHA-256 (hex) of the encrypted zip
+    pub file_hash: Option<String>,
+    pub error_message: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub download_count: i32,
+    pub last_downloaded_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+impl From<PatientExport> for PatientExportResponse {
+    fn from(export: PatientExport) -> Self {
+        Self {
+            id: export.id,
+            patient_id: export.patient_id,
+            requested_by: export.requested_by,
+            status: PatientExportStatus::from_str(&export.status)
+                .unwrap_or(PatientExportStatus::Failed),
+            file_size_bytes: export.file_size_bytes,
+            file_hash: export.file_hash,
+            error_message: export.error_message,
+            expires_at: export.expires_at,
+            download_count: export.download_count,
+            last_downloaded_at: export.last_downloaded_at,
+            created_at: export.created_at,
+            completed_at: export.completed_at,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn export(status: PatientExportStatus, expires_in_hours: i64) -> PatientExport {
+        let now = Utc::now();
+        PatientExport {
+            id: Uuid::new_v4(),
+            patient_id: Uuid::new_v4(),
+            requested_by: Uuid::new_v4(),
+            status: status.as_str().to_string(),
+            file_path: Some("/tmp/export.zip".to_string()),
+            file_size_bytes: Some(1024),
+            file_hash: None,
+            error_message: None,
+            expires_at: Some(now + Duration::hours(expires_in_hours)),
+            download_count: 0,
+            last_downloaded_at: None,
+            created_at: now,
+            completed_at: Some(now),
+        }
+    }
+
+    #[test]
+    fn test_patient_export_status_conversion() {
+        for status in [
+            PatientExportStatus::Processing,
+            PatientExportStatus::Completed,
+            PatientExportStatus::Failed,
+            PatientExportStatus::Expired,
+        ] {
+            assert_eq!(PatientExportStatus::from_str(status.as_str()), Some(status));
+        }
+        assert_eq!(PatientExportStatus::from_str("PENDING"), None);
+    }
+
+    #[test]
+    fn test_patient_export_is_downloadable() {
+        let now = Utc::now();
+        assert!(export(PatientExportStatus::Completed, 24).is_downloadable(now));
+        assert!(!export(PatientExportStatus::Completed, -1).is_downloadable(now));
+        assert!(!export(PatientExportStatus::Processing, 24).is_downloadable(now));
+        assert!(!export(PatientExportStatus::Expired, 24).is_downloadable(now));
+    }
+
+    #[test]
+    fn test_create_patient_export_request_debug_redacts_passphrase() {
+        let request = CreatePatientExportRequest {
+            passphrase: "correct horse battery staple".to_string(),
+        };
+        assert!(request.validate().is_ok());
+        assert!(!format!("{:?}", request).contains("horse"));
+
+        let short = CreatePatientExportRequest {
+            passphrase: "short".to_string(),
+        };
+        assert!(short.validate().is_err());
+    }
+}
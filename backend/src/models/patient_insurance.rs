@@ -1,7 +1,8 @@
 // Patient Insurance model with encrypted PHI/PII fields
-// All insurance information is encrypted using AES-256-GCM before storage
+// All insurance information is encrypted using AES-256-GCM before storage,
+// except the policy number: AES-SIV, so equal policies can be matched
 
-use crate::utils::encryption::EncryptionKey;
+use crate::utils::encryption::{DeterministicField, EncryptionKey};
 use anyhow::{Context, Result};
 use chrono::{NaiveDate, DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -245,7 +246,8 @@ impl PatientInsurance {
     ) -> Result<Self> {
         // Encrypt PHI/PII fields
         let encrypted_provider_name = key.encrypt(&data.provider_name)?;
-        let encrypted_policy_number = key.encrypt(&data.policy_number)?;
+        let encrypted_policy_number =
+            key.encrypt_deterministic(DeterministicField::PolicyNumber, &data.policy_number)?;
         let encrypted_group_number = key.encrypt_optional(&data.group_number)?;
         let encrypted_plan_name = key.encrypt_optional(&data.plan_name)?;
 
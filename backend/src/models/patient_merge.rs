@@ -0,0 +1,106 @@
+/*!
+ * Patient Merge Model
+ *
+ * Merging a duplicate patient record into the surviving one. The duplicate's
+ * clinical and administrative records are re-parented and the duplicate is
+ * kept as a read-only tombstone pointing to the surviving record
+ * (`merged_into_id`), so old references and the audit trail still resolve.
+ */
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::Validate;
+
+/// Request body for POST /api/v1/patients/:id/merge/:merge_id
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct MergePatientsRequest {
+    /// Must match the duplicate's medical record number, as a guard against
+    /// merging away the wrong record
+    #[validate(length(min = 1, max = 50))]
+    pub confirm_medical_record_number: String,
+    #[validate(length(max = 2000))]
+    pub reason: Option<String>,
+}
+
+/// Records moved to the surviving patient, per table
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PatientMergeSummary {
+    pub appointments: u64,
+    pub visits: u64,
+    pub visit_diagnoses: u64,
+    pub prescriptions: u64,
+    pub documents: u64,
+    pub attachments: u64,
+    pub allergies: u64,
+    /// Problems whose condition was not already active on the surviving
+    /// patient
+    pub problems: u64,
+    pub lab_orders: u64,
+    pub lab_results: u64,
+    pub imaging_orders: u64,
+    pub referrals: u64,
+    pub insurance: u64,
+    /// Insurance left on the duplicate because the surviving patient already
+    /// has one of the same type
+    pub insurance_not_moved: u64,
+    pub notifications: u64,
+    /// Communication log entries (absent from summaries recorded before the
+    /// log)
+    #[serde(default)]
+    pub communications: u64,
+    /// Message threads (absent from summaries recorded before messaging)
+    #[serde(default)]
+    pub message_threads: u64,
+    /// Visit surveys (absent from summaries recorded before surveys)
+    #[serde(default)]
+    pub visit_surveys: u64,
+    /// Televisit rooms (absent from summaries recorded before televisits)
+    #[serde(default)]
+    pub televisit_sessions: u64,
+    /// Waitlist entries (absent from summaries recorded before the waitlist)
+    #[serde(default)]
+    pub waitlist_entries: u64,
+}
+
+/// Result of a merge (API output)
+#[derive(Debug, Clone, Serialize)]
+pub struct PatientMergeResponse {
+    pub surviving_patient_id: Uuid,
+    pub surviving_medical_record_number: String,
+    pub merged_patient_id: Uuid,
+    pub merged_medical_record_number: String,
+    pub merged_at: DateTime<Utc>,
+    pub merged_by: Uuid,
+    pub summary: PatientMergeSummary,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_request_validation() {
+        let request: MergePatientsRequest =
+            serde_json::from_str(r#"{"confirm_medical_record_number": "MRN-0042"}"#).unwrap();
+        assert!(request.validate().is_ok());
+        assert!(request.reason.is_none());
+
+        let empty: MergePatientsRequest =
+            serde_json::from_str(r#"{"confirm_medical_record_number": ""}"#).unwrap();
+        assert!(empty.validate().is_err());
+    }
+
+    #[test]
+    fn test_merge_summary_serialization() {
+        let summary = PatientMergeSummary {
+            visits: 3,
+            insurance_not_moved: 1,
+            ..Default::default()
+        };
+        let json = serde_json::to_value(&summary).unwrap();
+        assert_eq!(json["visits"], 3);
+        assert_eq!(json["insurance_not_moved"], 1);
+        assert_eq!(json["appointments"], 0);
+    }
+}
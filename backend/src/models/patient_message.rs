@@ -0,0 +1,306 @@
+/*!
+ * Patient Message Model
+ *
+ * Secure threaded messaging between the practice and a patient. Staff work
+ * from the provider inbox; patients read and answer through the patient
+ * portal or a secure link. Subjects and bodies are encrypted. Attachments
+ * are documents of the patient record.
+ */
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+/// Most attachments per message
+pub const MAX_MESSAGE_ATTACHMENTS: usize = 10;
+
+/// Default page size of the provider inbox
+pub const DEFAULT_INBOX_LIMIT: i64 = 50;
+
+/// Largest page size of the provider inbox
+pub const MAX_INBOX_LIMIT: i64 = 200;
+
+/// Status of a conversation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum MessageThreadStatus {
+    Open,
+    /// No more replies; a new message from either side reopens it
+    Closed,
+}
+
+impl MessageThreadStatus {
+    /// Convert to database string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MessageThreadStatus::Open => "OPEN",
+            MessageThreadStatus::Closed => "CLOSED",
+        }
+    }
+}
+
+/// Side of the conversation a message comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum MessageSender {
+    /// A staff member, in the practice's name
+    Practice,
+    Patient,
+}
+
+impl MessageSender {
+    /// Convert to database string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MessageSender::Practice => "PRACTICE",
+            MessageSender::Patient => "PATIENT",
+        }
+    }
+}
+
+/// Thread row (encrypted), with the number of patient messages the practice
+/// has not read
+#[derive(Debug, Clone, FromRow)]
+pub struct PatientMessageThread {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub subject: String, // 🔒 Encrypted
+    pub status: String,
+    pub assigned_to: Option<Uuid>,
+    pub last_message_at: DateTime<Utc>,
+    pub closed_at: Option<DateTime<Utc>>,
+    pub closed_by: Option<Uuid>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub unread_count: i64,
+}
+
+/// Message row (encrypted)
+#[derive(Debug, Clone, FromRow)]
+pub struct PatientMessage {
+    pub id: Uuid,
+    pub thread_id: Uuid,
+    pub sender: String,
+    pub sender_user_id: Option<Uuid>,
+    pub body: String, // 🔒 Encrypted
+    pub created_at: DateTime<Utc>,
+    pub read_by_patient_at: Option<DateTime<Utc>>,
+    pub read_by_practice_at: Option<DateTime<Utc>>,
+    pub read_by: Option<Uuid>,
+}
+
+/// Attachment of a message, joined with the patient attachment and its file
+#[derive(Debug, Clone, FromRow)]
+pub struct PatientMessageAttachment {
+    pub message_id: Uuid,
+    pub attachment_id: Uuid,
+    pub patient_id: Uuid,
+    pub title: String,
+    pub original_filename: String,
+    pub mime_type: String,
+    pub file_size_bytes: i64,
+}
+
+/// Document shared in a message (API output)
+#[derive(Debug, Clone, Serialize)]
+pub struct MessageAttachmentResponse {
+    pub attachment_id: Uuid,
+    pub title: String,
+    pub original_filename: String,
+    pub mime_type: String,
+    pub file_size_bytes: i64,
+    pub download_url: String,
+}
+
+impl From<PatientMessageAttachment> for MessageAttachmentResponse {
+    fn from(attachment: PatientMessageAttachment) -> Self {
+        Self {
+            download_url: format!(
+                "/api/v1/patients/{}/attachments/{}/download",
+                attachment.patient_id, attachment.attachment_id
+            ),
+            attachment_id: attachment.attachment_id,
+            title: attachment.title,
+            original_filename: attachment.original_filename,
+            mime_type: attachment.mime_type,
+            file_size_bytes: attachment.file_size_bytes,
+        }
+    }
+}
+
+/// Message (API output, decrypted)
+#[derive(Debug, Clone, Serialize)]
+pub struct PatientMessageResponse {
+    pub id: Uuid,
+    pub thread_id: Uuid,
+    pub sender: String,
+    pub sender_user_id: Option<Uuid>,
+    pub body: String,
+    pub attachments: Vec<MessageAttachmentResponse>,
+    pub created_at: DateTime<Utc>,
+    /// When the patient read a practice message
+    pub read_by_patient_at: Option<DateTime<Utc>>,
+    /// When the practice read a patient message, and who
+    pub read_by_practice_at: Option<DateTime<Utc>>,
+    pub read_by: Option<Uuid>,
+}
+
+/// Thread (API output, decrypted)
+#[derive(Debug, Clone, Serialize)]
+pub struct MessageThreadResponse {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub patient_name: String,
+    pub subject: String,
+    pub status: String,
+    pub assigned_to: Option<Uuid>,
+    pub last_message_at: DateTime<Utc>,
+    /// Patient messages the practice has not read
+    pub unread_count: i64,
+    pub closed_at: Option<DateTime<Utc>>,
+    pub closed_by: Option<Uuid>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Thread with its messages, oldest first
+#[derive(Debug, Clone, Serialize)]
+pub struct MessageThreadDetailResponse {
+    #[serde(flatten)]
+    pub thread: MessageThreadResponse,
+    pub messages: Vec<PatientMessageResponse>,
+}
+
+/// Page of the provider inbox, most recent activity first
+#[derive(Debug, Clone, Serialize)]
+pub struct MessageInboxResponse {
+    pub threads: Vec<MessageThreadResponse>,
+    pub total: i64,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+/// Unread patient messages, for the inbox badge
+#[derive(Debug, Clone, Serialize)]
+pub struct MessageUnreadCountResponse {
+    /// Threads with unread patient messages
+    pub threads: i64,
+    pub messages: i64,
+    /// Of which in threads assigned to the caller
+    pub assigned_to_me: i64,
+}
+
+/// Result of POST /api/v1/message-threads/:id/read
+#[derive(Debug, Clone, Serialize)]
+pub struct MarkMessagesReadResponse {
+    /// Patient messages marked read by this call
+    pub marked_read: u64,
+}
+
+/// Request body for POST /api/v1/patients/:id/message-threads
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct CreateMessageThreadRequest {
+    #[validate(length(min = 1, max = 255, message = "Subject must be 1-255 characters"))]
+    pub subject: String,
+    #[validate(length(min = 1, max = 10000, message = "Message must be 1-10000 characters"))]
+    pub body: String,
+    /// Documents of the patient record to share
+    #[serde(default)]
+    #[validate(length(max = 10, message = "At most 10 attachments per message"))]
+    pub attachment_ids: Vec<Uuid>,
+    /// Staff member handling the conversation; the whole practice when absent
+    pub assigned_to: Option<Uuid>,
+}
+
+/// Request body for POST /api/v1/message-threads/:id/messages
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct CreatePatientMessageRequest {
+    #[validate(length(min = 1, max = 10000, message = "Message must be 1-10000 characters"))]
+    pub body: String,
+    /// Documents of the patient record to share
+    #[serde(default)]
+    #[validate(length(max = 10, message = "At most 10 attachments per message"))]
+    pub attachment_ids: Vec<Uuid>,
+}
+
+/// Request body for PUT /api/v1/message-threads/:id
+///
+/// Absent fields are left unchanged; `unassign` hands the conversation back
+/// to the whole practice.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct UpdateMessageThreadRequest {
+    pub status: Option<MessageThreadStatus>,
+    pub assigned_to: Option<Uuid>,
+    #[serde(default)]
+    pub unassign: bool,
+}
+
+/// Query parameters for GET /api/v1/message-threads
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct MessageInboxQuery {
+    pub status: Option<MessageThreadStatus>,
+    pub patient_id: Option<Uuid>,
+    pub assigned_to: Option<Uuid>,
+    /// Only threads assigned to the caller
+    #[serde(default)]
+    pub assigned_to_me: bool,
+    /// Only threads with unread patient messages
+    #[serde(default)]
+    pub unread_only: bool,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+impl MessageInboxQuery {
+    /// Page size, clamped to 1-MAX_INBOX_LIMIT
+    pub fn limit(&self) -> i64 {
+        self.limit
+            .unwrap_or(DEFAULT_INBOX_LIMIT)
+            .clamp(1, MAX_INBOX_LIMIT)
+    }
+
+    /// Offset, never negative
+    pub fn offset(&self) -> i64 {
+        self.offset.unwrap_or(0).max(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_thread_request_validation() {
+        let request: CreateMessageThreadRequest = serde_json::from_str(
+            r#"{"subject": "Lab results", "body": "Your results are attached."}"#,
+        )
+        .unwrap();
+        assert!(request.validate().is_ok());
+        assert!(request.attachment_ids.is_empty());
+
+        let too_many = CreatePatientMessageRequest {
+            body: "See attached".to_string(),
+            attachment_ids: (0..=MAX_MESSAGE_ATTACHMENTS)
+                .map(|_| Uuid::new_v4())
+                .collect(),
+        };
+        assert!(too_many.validate().is_err());
+
+        let empty: CreatePatientMessageRequest = serde_json::from_str(r#"{"body": ""}"#).unwrap();
+        assert!(empty.validate().is_err());
+    }
+
+    #[test]
+    fn test_inbox_query_paging() {
+        let query: MessageInboxQuery =
+            serde_json::from_str(r#"{"status": "OPEN", "limit": 1000}"#).unwrap();
+        assert_eq!(query.status, Some(MessageThreadStatus::Open));
+        assert_eq!(query.limit(), MAX_INBOX_LIMIT);
+        assert_eq!(query.offset(), 0);
+        assert!(!query.unread_only);
+    }
+}
@@ -0,0 +1,57 @@
+/*!
+ * Patient Photo Model
+ *
+ * Identification photo on the patient record. The photo and a JPEG
+ * thumbnail are stored through `FileUploadService`, encrypted at rest, and
+ * served only by the patient photo endpoints.
+ */
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use uuid::Uuid;
+
+/// Largest width or height accepted when decoding a photo, in pixels
+pub const MAX_PATIENT_PHOTO_DIMENSION: u32 = 8000;
+
+/// Patient photo metadata (API output)
+#[derive(Debug, Clone, Serialize)]
+pub struct PatientPhotoResponse {
+    pub patient_id: Uuid,
+    pub mime_type: String,
+    pub file_size_bytes: i64,
+    pub width: u32,
+    pub height: u32,
+    pub thumbnail_width: u32,
+    pub thumbnail_height: u32,
+    pub uploaded_at: DateTime<Utc>,
+    pub url: String,
+    pub thumbnail_url: String,
+}
+
+/// URL of a patient's photo (also stored in `patients.photo_url`)
+pub fn patient_photo_url(patient_id: Uuid) -> String {
+    format!("/api/v1/patients/{}/photo", patient_id)
+}
+
+/// URL of a patient's photo thumbnail
+pub fn patient_photo_thumbnail_url(patient_id: Uuid) -> String {
+    format!("/api/v1/patients/{}/photo/thumbnail", patient_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_patient_photo_urls() {
+        let id = Uuid::nil();
+        assert_eq!(
+            patient_photo_url(id),
+            "/api/v1/patients/00000000-0000-0000-0000-000000000000/photo"
+        );
+        assert_eq!(
+            patient_photo_thumbnail_url(id),
+            "/api/v1/patients/00000000-0000-0000-0000-000000000000/photo/thumbnail"
+        );
+    }
+}
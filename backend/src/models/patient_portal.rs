@@ -0,0 +1,247 @@
+/*!
+ * Patient Portal Model
+ *
+ * Patients sign in with a one-time link emailed to the address on their
+ * record and get a short portal session. The portal token is its own token
+ * type (`PortalClaims`), only accepted on `/api/portal/v1`, and database
+ * access runs as role 'PATIENT' limited to the patient's own rows.
+ */
+
+use chrono::{DateTime, NaiveDate, Utc};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::models::patient::{validate_phone, Address, ContactMethod, PatientDto};
+
+/// Setting that turns the portal on for the practice
+pub const PORTAL_ENABLED_SETTING_KEY: &str = "portal.enabled";
+
+/// Validity of a sign-in link
+pub const PORTAL_LOGIN_LINK_MINUTES: i64 = 15;
+
+/// Length of a portal session; patients sign in again afterwards
+pub const PORTAL_SESSION_MINUTES: i64 = 30;
+
+/// Most sign-in links sent to one patient per link validity window
+pub const MAX_PORTAL_LOGIN_LINKS: i64 = 3;
+
+/// Most upcoming appointments listed
+pub const MAX_PORTAL_UPCOMING_APPOINTMENTS: i64 = 50;
+
+/// Patient signed in to the portal (request extension set by
+/// `portal_auth_middleware`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PortalPatient {
+    pub patient_id: Uuid,
+    pub session_id: Uuid,
+}
+
+/// Generate a sign-in link token (32 random bytes, hex)
+pub fn generate_portal_login_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// Hash a sign-in link token for storage/lookup
+pub fn hash_portal_login_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.trim().as_bytes()))
+}
+
+/// Patient a sign-in link may be sent to (`portal_login_candidates()`,
+/// encrypted)
+#[derive(Debug, Clone, FromRow)]
+pub struct PortalLoginCandidate {
+    pub patient_id: Uuid,
+    pub first_name: String,    // 🔒 Encrypted
+    pub date_of_birth: String, // 🔒 Encrypted
+    pub email: String,         // 🔒 Encrypted
+}
+
+/// Portal session row
+#[derive(Debug, Clone, FromRow)]
+pub struct PortalSession {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+/// Upcoming appointment as shown to the patient
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct PortalAppointmentResponse {
+    pub id: Uuid,
+    pub scheduled_start: DateTime<Utc>,
+    pub scheduled_end: DateTime<Utc>,
+    pub duration_minutes: i32,
+    #[serde(rename = "type")]
+    pub appointment_type: String,
+    pub status: String,
+    pub provider_name: String,
+    pub confirmed_at: Option<DateTime<Utc>>,
+}
+
+/// Signed document of the patient
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct PortalDocumentResponse {
+    pub id: Uuid,
+    pub document_type: String,
+    pub document_title: String,
+    pub document_filename: String,
+    pub file_size_bytes: Option<i64>,
+    pub visit_date: Option<NaiveDate>,
+    pub signed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// The patient's own record, as far as the portal shows it (decrypted)
+#[derive(Debug, Clone, Serialize)]
+pub struct PortalProfileResponse {
+    pub id: Uuid,
+    pub medical_record_number: String,
+    pub first_name: String,
+    pub last_name: String,
+    pub date_of_birth: NaiveDate,
+    pub phone_primary: Option<String>,
+    pub phone_secondary: Option<String>,
+    pub email: Option<String>,
+    pub address: Option<Address>,
+    pub preferred_contact_method: ContactMethod,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<PatientDto> for PortalProfileResponse {
+    fn from(patient: PatientDto) -> Self {
+        Self {
+            id: patient.id,
+            medical_record_number: patient.medical_record_number,
+            first_name: patient.first_name,
+            last_name: patient.last_name,
+            date_of_birth: patient.date_of_birth,
+            phone_primary: patient.phone_primary,
+            phone_secondary: patient.phone_secondary,
+            email: patient.email,
+            address: patient.address,
+            preferred_contact_method: patient.preferred_contact_method,
+            updated_at: patient.updated_at,
+        }
+    }
+}
+
+/// Result of POST /api/portal/v1/auth/session
+#[derive(Debug, Clone, Serialize)]
+pub struct PortalSessionResponse {
+    pub access_token: String,
+    pub token_type: String,
+    /// Seconds until the session ends
+    pub expires_in: i64,
+    pub expires_at: DateTime<Utc>,
+    pub patient: PortalProfileResponse,
+}
+
+/// Result of POST /api/portal/v1/auth/login-link
+///
+/// The same whether or not a link was sent, so the endpoint cannot be used to
+/// find out who is a patient of the practice.
+#[derive(Debug, Clone, Serialize)]
+pub struct PortalLoginLinkResponse {
+    pub message: String,
+}
+
+/// Request body for POST /api/portal/v1/auth/login-link
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct PortalLoginLinkRequest {
+    #[validate(email(message = "Invalid email address"))]
+    pub email: String,
+    pub date_of_birth: NaiveDate,
+}
+
+/// Request body for POST /api/portal/v1/auth/session
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct PortalSessionRequest {
+    #[validate(length(min = 1, max = 128, message = "Invalid sign-in link"))]
+    pub token: String,
+}
+
+/// Request body for PUT /api/portal/v1/me/contact
+///
+/// Absent fields are left unchanged.
+#[derive(Debug, Clone, Default, Deserialize, Validate)]
+pub struct UpdatePortalContactRequest {
+    #[validate(custom(function = "validate_phone"))]
+    pub phone_primary: Option<String>,
+    #[validate(custom(function = "validate_phone"))]
+    pub phone_secondary: Option<String>,
+    #[validate(email(message = "Invalid email address"))]
+    pub email: Option<String>,
+    pub address: Option<Address>,
+    pub preferred_contact_method: Option<ContactMethod>,
+}
+
+impl UpdatePortalContactRequest {
+    /// Whether the request changes anything
+    pub fn is_empty(&self) -> bool {
+        self.phone_primary.is_none()
+            && self.phone_secondary.is_none()
+            && self.email.is_none()
+            && self.address.is_none()
+            && self.preferred_contact_method.is_none()
+    }
+
+    /// Names of the fields set, for the audit log (never the values)
+    pub fn changed_fields(&self) -> Vec<&'static str> {
+        [
+            ("phone_primary", self.phone_primary.is_some()),
+            ("phone_secondary", self.phone_secondary.is_some()),
+            ("email", self.email.is_some()),
+            ("address", self.address.is_some()),
+            (
+                "preferred_contact_method",
+                self.preferred_contact_method.is_some(),
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(field, set)| set.then_some(field))
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_login_token_hash() {
+        let token = generate_portal_login_token();
+        assert_eq!(token.len(), 64);
+        assert_ne!(token, generate_portal_login_token());
+        assert_eq!(hash_portal_login_token(&token), hash_portal_login_token(&format!(" {} ", token)));
+        assert_ne!(hash_portal_login_token(&token), token);
+    }
+
+    #[test]
+    fn test_contact_request_fields() {
+        let request: UpdatePortalContactRequest = serde_json::from_str(
+            r#"{"email": "anna.rossi@example.com", "preferred_contact_method": "EMAIL"}"#,
+        )
+        .unwrap();
+        assert!(request.validate().is_ok());
+        assert!(!request.is_empty());
+        assert_eq!(
+            request.changed_fields(),
+            vec!["email", "preferred_contact_method"]
+        );
+
+        let invalid: UpdatePortalContactRequest =
+            serde_json::from_str(r#"{"email": "not-an-email"}"#).unwrap();
+        assert!(invalid.validate().is_err());
+
+        assert!(UpdatePortalContactRequest::default().is_empty());
+    }
+}
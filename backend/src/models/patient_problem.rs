@@ -0,0 +1,191 @@
+/*!
+ * Patient Problem Model
+ *
+ * Longitudinal problem list: ongoing conditions (ICD-10 coded) kept across
+ * visits, as opposed to `VisitDiagnosis`, which records what was assessed
+ * at a single visit. A problem links the visits where it was addressed and
+ * may come from a promoted visit diagnosis. Clinical notes are encrypted.
+ */
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::utils::encryption::EncryptionKey;
+
+/// Whether a problem is still ongoing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ProblemStatus {
+    Active,
+    Resolved,
+}
+
+impl ProblemStatus {
+    /// Convert to database string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProblemStatus::Active => "ACTIVE",
+            ProblemStatus::Resolved => "RESOLVED",
+        }
+    }
+}
+
+/// Problem row (encrypted)
+#[derive(Debug, Clone, FromRow)]
+pub struct PatientProblem {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub icd10_code: String,
+    pub icd10_description: String,
+    pub status: String,
+    pub onset_date: Option<NaiveDate>,
+    pub resolved_date: Option<NaiveDate>,
+    pub clinical_notes: Option<String>, // 🔒 Encrypted
+    pub source_diagnosis_id: Option<Uuid>,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_by: Option<Uuid>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl PatientProblem {
+    /// Decrypt into the API representation
+    pub fn decrypt(
+        &self,
+        key: &EncryptionKey,
+        linked_visits: Vec<LinkedVisit>,
+    ) -> Result<PatientProblemResponse> {
+        Ok(PatientProblemResponse {
+            id: self.id,
+            patient_id: self.patient_id,
+            icd10_code: self.icd10_code.clone(),
+            icd10_description: self.icd10_description.clone(),
+            status: self.status.clone(),
+            onset_date: self.onset_date,
+            resolved_date: self.resolved_date,
+            clinical_notes: key
+                .decrypt_optional(&self.clinical_notes)
+                .context("Failed to decrypt problem clinical_notes")?,
+            source_diagnosis_id: self.source_diagnosis_id,
+            linked_visits,
+            created_by: self.created_by,
+            created_at: self.created_at,
+            updated_by: self.updated_by,
+            updated_at: self.updated_at,
+        })
+    }
+}
+
+/// Visit where a problem was addressed
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct LinkedVisit {
+    #[serde(skip)]
+    pub problem_id: Uuid,
+    pub visit_id: Uuid,
+    pub visit_date: NaiveDate,
+    pub linked_at: DateTime<Utc>,
+}
+
+/// Problem (API output, decrypted)
+#[derive(Debug, Clone, Serialize)]
+pub struct PatientProblemResponse {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub icd10_code: String,
+    pub icd10_description: String,
+    pub status: String,
+    pub onset_date: Option<NaiveDate>,
+    pub resolved_date: Option<NaiveDate>,
+    pub clinical_notes: Option<String>,
+    pub source_diagnosis_id: Option<Uuid>,
+    /// Most recent visit first
+    pub linked_visits: Vec<LinkedVisit>,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_by: Option<Uuid>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Request body for POST /api/v1/patients/:id/problems
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct CreatePatientProblemRequest {
+    #[validate(length(min = 1, max = 10, message = "ICD-10 code must be 1-10 characters"))]
+    pub icd10_code: String,
+    #[validate(length(
+        min = 1,
+        max = 500,
+        message = "ICD-10 description must be 1-500 characters"
+    ))]
+    pub icd10_description: String,
+    pub onset_date: Option<NaiveDate>,
+    #[validate(length(max = 5000, message = "Clinical notes too long (max 5000 chars)"))]
+    pub clinical_notes: Option<String>,
+    /// Visit of the same patient to link the problem to
+    pub visit_id: Option<Uuid>,
+}
+
+/// Request body for PUT /api/v1/patients/:id/problems/:problem_id
+///
+/// Absent fields are left unchanged. Setting `status` to RESOLVED without a
+/// `resolved_date` resolves the problem today; setting it back to ACTIVE
+/// clears the resolution date.
+#[derive(Debug, Clone, Default, Deserialize, Validate)]
+pub struct UpdatePatientProblemRequest {
+    #[validate(length(
+        min = 1,
+        max = 500,
+        message = "ICD-10 description must be 1-500 characters"
+    ))]
+    pub icd10_description: Option<String>,
+    pub status: Option<ProblemStatus>,
+    pub onset_date: Option<NaiveDate>,
+    pub resolved_date: Option<NaiveDate>,
+    #[validate(length(max = 5000, message = "Clinical notes too long (max 5000 chars)"))]
+    pub clinical_notes: Option<String>,
+}
+
+/// Request body for POST /api/v1/patients/:id/problems/:problem_id/visits
+#[derive(Debug, Clone, Deserialize)]
+pub struct LinkProblemVisitRequest {
+    pub visit_id: Uuid,
+}
+
+/// Query parameters for GET /api/v1/patients/:id/problems
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ListPatientProblemsQuery {
+    pub status: Option<ProblemStatus>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_problem_request_validation() {
+        let request: CreatePatientProblemRequest = serde_json::from_str(
+            r#"{"icd10_code": "I10", "icd10_description": "Essential hypertension"}"#,
+        )
+        .unwrap();
+        assert!(request.validate().is_ok());
+        assert!(request.visit_id.is_none());
+
+        let too_long: CreatePatientProblemRequest =
+            serde_json::from_str(r#"{"icd10_code": "I10.123456789", "icd10_description": "x"}"#)
+                .unwrap();
+        assert!(too_long.validate().is_err());
+    }
+
+    #[test]
+    fn test_update_problem_request_status() {
+        let request: UpdatePatientProblemRequest =
+            serde_json::from_str(r#"{"status": "RESOLVED"}"#).unwrap();
+        assert!(request.validate().is_ok());
+        assert_eq!(request.status, Some(ProblemStatus::Resolved));
+        assert_eq!(request.status.unwrap().as_str(), "RESOLVED");
+        assert!(request.resolved_date.is_none());
+    }
+}
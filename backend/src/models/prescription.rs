@@ -91,6 +91,42 @@ pub struct DrugInteractionWarning {
     pub description: String,
 }
 
+/// How a prescribed drug matched a recorded allergy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AllergyMatchType {
+    /// The drug is (or contains) the allergen itself
+    Substance,
+    /// The drug belongs to the same class as the allergen (e.g. penicillins)
+    Class,
+    /// The drug belongs to a class known to cross-react with the allergen's
+    CrossReactivity,
+}
+
+impl AllergyMatchType {
+    /// Convert to database string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AllergyMatchType::Substance => "SUBSTANCE",
+            AllergyMatchType::Class => "CLASS",
+            AllergyMatchType::CrossReactivity => "CROSS_REACTIVITY",
+        }
+    }
+}
+
+/// Conflict between a prescribed drug and an active patient allergy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DrugAllergyAlert {
+    pub allergy_id: Uuid,
+    pub substance: String,
+    pub severity: Option<String>,
+    pub reaction: Option<String>,
+    pub match_type: AllergyMatchType,
+    /// Drug class shared by allergen and drug (class and cross-reactivity matches)
+    pub drug_class: Option<String>,
+    pub message: String,
+}
+
 /// Prescription model - database representation with ENCRYPTED fields
 /// Fields marked with 🔒 are encrypted in the database
 #[derive(Debug, Clone, FromRow)]
@@ -139,7 +175,7 @@ pub struct Prescription {
     pub has_interactions: bool,
     pub interaction_warnings: Option<sqlx::types::JsonValue>,
 
-    // E-Prescription (future enhancement)
+    // E-Prescription (Sistema TS NRE, see e_prescription)
     pub e_prescription_id: Option<String>,
     pub e_prescription_sent_at: Option<DateTime<Utc>>,
     pub e_prescription_status: Option<String>,
@@ -202,6 +238,11 @@ pub struct CreatePrescriptionRequest {
 
     // Drug interaction warnings detected at creation time
     pub interaction_warnings: Option<Vec<DrugInteractionWarning>>,
+
+    /// Required to prescribe a drug that matches one of the patient's active
+    /// allergies; recorded with the prescription and audited
+    #[validate(length(min = 1, max = 1000, message = "Allergy override reason must be 1-1000 characters"))]
+    pub allergy_override_reason: Option<String>,
 }
 
 /// Prescription update request (API input with decrypted data)
@@ -287,6 +328,9 @@ pub struct PrescriptionResponse {
     // Warnings
     pub has_interactions: bool,
     pub interaction_warnings: Option<Vec<DrugInteractionWarning>>,
+    /// Allergy alerts overridden to create the prescription (create response only)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allergy_alerts: Vec<DrugAllergyAlert>,
 
     // E-Prescription
     pub e_prescription_id: Option<String>,
@@ -300,6 +344,56 @@ pub struct PrescriptionResponse {
     pub updated_by: Option<Uuid>,
 }
 
+/// Packages of the same medication a single SSN prescription can prescribe
+pub const SSN_MAX_PACKAGES: i32 = 2;
+
+/// Priority class of an SSN prescription (box printed on the "ricetta")
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SsnPriority {
+    /// U - within 72 hours
+    Urgent,
+    /// B - within 10 days
+    Short,
+    /// D - within 30 days (visits) or 60 days (diagnostics)
+    Deferrable,
+    /// P - within 120 days
+    Scheduled,
+}
+
+impl SsnPriority {
+    pub const ALL: [SsnPriority; 4] = [
+        SsnPriority::Urgent,
+        SsnPriority::Short,
+        SsnPriority::Deferrable,
+        SsnPriority::Scheduled,
+    ];
+
+    /// Letter printed in the priority box
+    pub fn code(&self) -> &'static str {
+        match self {
+            SsnPriority::Urgent => "U",
+            SsnPriority::Short => "B",
+            SsnPriority::Deferrable => "D",
+            SsnPriority::Scheduled => "P",
+        }
+    }
+}
+
+/// Request body for POST /api/v1/prescriptions/:id/ssn-print
+#[derive(Debug, Clone, Default, Deserialize, Validate)]
+pub struct GenerateSsnPrescriptionRequest {
+    /// SSN_PRESCRIPTION template to use; defaults to the default one
+    pub template_id: Option<Uuid>,
+    /// Printed in the exemption box; the patient is "non esente" without one
+    #[validate(length(min = 1, max = 6, message = "Exemption code must be 1-6 characters"))]
+    pub exemption_code: Option<String>,
+    /// No priority box is checked without one
+    pub priority: Option<SsnPriority>,
+    #[validate(length(max = 500, message = "Notes too long (max 500 chars)"))]
+    pub notes: Option<String>,
+}
+
 /// Medication search result
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MedicationSearchResult {
@@ -400,6 +494,7 @@ impl Prescription {
             last_refill_date: self.last_refill_date,
             has_interactions: self.has_interactions,
             interaction_warnings,
+            allergy_alerts: Vec::new(),
             e_prescription_id: self.e_prescription_id.clone(),
             e_prescription_sent_at: self.e_prescription_sent_at,
             e_prescription_status: self.e_prescription_status.clone(),
@@ -461,4 +556,16 @@ mod tests {
         let route2 = RouteOfAdministration::Intravenous;
         assert_eq!(route2, RouteOfAdministration::Intravenous);
     }
+
+    #[test]
+    fn test_generate_ssn_prescription_request() {
+        let request: GenerateSsnPrescriptionRequest =
+            serde_json::from_str(r#"{"exemption_code": "013", "priority": "SHORT"}"#).unwrap();
+        assert!(request.validate().is_ok());
+        assert_eq!(request.priority.map(|p| p.code()), Some("B"));
+
+        let too_long: GenerateSsnPrescriptionRequest =
+            serde_json::from_str(r#"{"exemption_code": "0130000"}"#).unwrap();
+        assert!(too_long.validate().is_err());
+    }
 }
@@ -0,0 +1,223 @@
+/*!
+ * Prescription Print Batch Model
+ *
+ * A batch of prescriptions merged into one PDF (one prescription per page)
+ * for printing and signing in one go. Small batches are rendered during the
+ * request; larger ones in the background, and clients poll the batch until
+ * it is COMPLETED or FAILED.
+ */
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+/// Most prescriptions a batch can print
+pub const MAX_PRINT_BATCH_PRESCRIPTIONS: usize = 200;
+
+/// Batches up to this size are rendered during the request
+pub const INLINE_PRINT_BATCH_LIMIT: usize = 10;
+
+/// Status of a print batch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum PrintBatchStatus {
+    Processing,
+    Completed,
+    Failed,
+}
+
+impl PrintBatchStatus {
+    /// Convert to database string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PrintBatchStatus::Processing => "PROCESSING",
+            PrintBatchStatus::Completed => "COMPLETED",
+            PrintBatchStatus::Failed => "FAILED",
+        }
+    }
+
+    /// Parse from database string
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "PROCESSING" => Some(PrintBatchStatus::Processing),
+            "COMPLETED" => Some(PrintBatchStatus::Completed),
+            "FAILED" => Some(PrintBatchStatus::Failed),
+            _ => None,
+        }
+    }
+}
+
+/// Layout each prescription is printed with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum PrescriptionPrintLayout {
+    /// Default PRESCRIPTION template
+    #[default]
+    Standard,
+    /// SSN "ricetta" (default SSN_PRESCRIPTION template)
+    Ssn,
+}
+
+impl PrescriptionPrintLayout {
+    /// Convert to database string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PrescriptionPrintLayout::Standard => "STANDARD",
+            PrescriptionPrintLayout::Ssn => "SSN",
+        }
+    }
+
+    /// Parse from database string
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "STANDARD" => Some(PrescriptionPrintLayout::Standard),
+            "SSN" => Some(PrescriptionPrintLayout::Ssn),
+            _ => None,
+        }
+    }
+}
+
+/// Print batch row
+#[derive(Debug, Clone, FromRow)]
+pub struct PrescriptionPrintBatch {
+    pub id: Uuid,
+    pub requested_by: Uuid,
+    pub status: String,
+    pub layout: String,
+    pub prescription_ids: Vec<Uuid>,
+    pub file_path: Option<String>,
+    pub file_size_bytes: Option<i64>,
+    pub file_hash: Option<String>,
+    pub error_message: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+impl PrescriptionPrintBatch {
+    /// Whether the merged PDF can be downloaded
+    pub fn is_downloadable(&self) -> bool {
+        self.status == PrintBatchStatus::Completed.as_str() && self.file_path.is_some()
+    }
+
+    /// Filename offered to the client
+    pub fn download_filename(&self) -> String {
+        format!(
+            "prescriptions_{}.pdf",
+            self.created_at.format("%Y%m%d_%H%M%S")
+        )
+    }
+}
+
+/// Request body for POST /api/v1/prescriptions/print-batches
+///
+/// Either `prescription_ids` or `today` selects the prescriptions.
+#[derive(Debug, Clone, Default, Deserialize, Validate)]
+pub struct CreatePrescriptionPrintBatchRequest {
+    /// Prescriptions in print order
+    #[serde(default)]
+    #[validate(length(max = 200, message = "At most 200 prescriptions per batch"))]
+    pub prescription_ids: Vec<Uuid>,
+
+    /// Print every prescription written today that is not cancelled
+    #[serde(default)]
+    pub today: bool,
+
+    #[serde(default)]
+    pub layout: PrescriptionPrintLayout,
+}
+
+impl CreatePrescriptionPrintBatchRequest {
+    /// Whether exactly one way of selecting prescriptions is used
+    pub fn has_single_selection(&self) -> bool {
+        self.today == self.prescription_ids.is_empty()
+    }
+
+    /// Requested prescription ids without duplicates, in request order
+    pub fn resolved_ids(&self) -> Vec<Uuid> {
+        let mut ids = Vec::with_capacity(self.prescription_ids.len());
+        for id in &self.prescription_ids {
+            if !ids.contains(id) {
+                ids.push(*id);
+            }
+        }
+        ids
+    }
+}
+
+/// Print batch (API output)
+#[derive(Debug, Clone, Serialize)]
+pub struct PrescriptionPrintBatchResponse {
+    pub id: Uuid,
+    pub requested_by: Uuid,
+    pub status: PrintBatchStatus,
+    pub layout: PrescriptionPrintLayout,
+    pub prescription_ids: Vec<Uuid>,
+    pub file_size_bytes: Option<i64>,
+    /// SHA-256 (hex) of the merged PDF
+    pub file_hash: Option<String>,
+    pub error_message: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+impl From<PrescriptionPrintBatch> for PrescriptionPrintBatchResponse {
+    fn from(batch: PrescriptionPrintBatch) -> Self {
+        Self {
+            id: batch.id,
+            requested_by: batch.requested_by,
+            status: PrintBatchStatus::from_str(&batch.status).unwrap_or(PrintBatchStatus::Failed),
+            layout: PrescriptionPrintLayout::from_str(&batch.layout).unwrap_or_default(),
+            prescription_ids: batch.prescription_ids,
+            file_size_bytes: batch.file_size_bytes,
+            file_hash: batch.file_hash,
+            error_message: batch.error_message,
+            created_at: batch.created_at,
+            completed_at: batch.completed_at,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_print_batch_status_conversion() {
+        for status in [
+            PrintBatchStatus::Processing,
+            PrintBatchStatus::Completed,
+            PrintBatchStatus::Failed,
+        ] {
+            assert_eq!(PrintBatchStatus::from_str(status.as_str()), Some(status));
+        }
+        assert_eq!(PrintBatchStatus::from_str("EXPIRED"), None);
+    }
+
+    #[test]
+    fn test_print_batch_request_selection() {
+        let id = Uuid::new_v4();
+        let request: CreatePrescriptionPrintBatchRequest =
+            serde_json::from_value(serde_json::json!({
+                "prescription_ids": [id, id],
+                "layout": "SSN",
+            }))
+            .unwrap();
+        assert!(request.has_single_selection());
+        assert_eq!(request.resolved_ids(), vec![id]);
+        assert_eq!(request.layout, PrescriptionPrintLayout::Ssn);
+
+        let today: CreatePrescriptionPrintBatchRequest =
+            serde_json::from_str(r#"{"today": true}"#).unwrap();
+        assert!(today.has_single_selection());
+        assert_eq!(today.layout, PrescriptionPrintLayout::Standard);
+
+        let both = CreatePrescriptionPrintBatchRequest {
+            today: true,
+            ..request
+        };
+        assert!(!both.has_single_selection());
+        assert!(!CreatePrescriptionPrintBatchRequest::default().has_single_selection());
+    }
+}
@@ -0,0 +1,129 @@
+/*!
+ * Prescription Refill Models
+ *
+ * Refill tracking for active prescriptions. How long the prescribed quantity
+ * lasts (`days_supply`) is estimated from the frequency and quantity; the
+ * supply runs out `days_supply` days after the last refill (or the start of
+ * therapy), and a prescription past that date is overdue for a refill.
+ */
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::Validate;
+
+/// Default days between reminders about the same prescription
+pub const DEFAULT_REFILL_REMINDER_INTERVAL_DAYS: i32 = 7;
+
+/// Query parameters for GET /api/v1/prescriptions/overdue-refills
+#[derive(Debug, Clone, Default, Deserialize, Validate)]
+pub struct OverdueRefillsQuery {
+    /// Days past the expected refill date before a prescription is listed
+    #[serde(default)]
+    #[validate(range(min = 0, max = 90, message = "grace_days must be between 0 and 90"))]
+    pub grace_days: i32,
+
+    /// Only this patient's prescriptions
+    pub patient_id: Option<Uuid>,
+}
+
+/// Prescription overdue for a refill (API output)
+#[derive(Debug, Clone, Serialize)]
+pub struct OverdueRefill {
+    pub prescription_id: Uuid,
+    pub patient_id: Uuid,
+    pub patient_name: String,
+    pub provider_id: Uuid,
+    pub medication_name: String,
+    pub dosage: String,
+    pub frequency: String,
+    pub days_supply: i32,
+    pub expected_refill_date: NaiveDate,
+    pub days_overdue: i64,
+    pub last_refill_date: Option<NaiveDate>,
+    pub refills_remaining: Option<i32>,
+    /// No refills left: the patient needs a new prescription
+    pub needs_new_prescription: bool,
+    pub last_refill_reminder_at: Option<DateTime<Utc>>,
+}
+
+/// Request body for POST /api/v1/prescriptions/overdue-refills/reminders
+///
+/// Without `prescription_ids`, every overdue prescription is considered.
+#[derive(Debug, Clone, Default, Deserialize, Validate)]
+pub struct SendRefillRemindersRequest {
+    #[serde(default)]
+    #[validate(length(max = 500, message = "At most 500 prescriptions per request"))]
+    pub prescription_ids: Vec<Uuid>,
+
+    #[serde(default)]
+    #[validate(range(min = 0, max = 90, message = "grace_days must be between 0 and 90"))]
+    pub grace_days: i32,
+
+    /// Skip prescriptions reminded less than this many days ago (default 7)
+    #[validate(range(min = 1, max = 90, message = "interval_days must be between 1 and 90"))]
+    pub interval_days: Option<i32>,
+}
+
+impl SendRefillRemindersRequest {
+    /// Minimum days between reminders about the same prescription
+    pub fn interval_days(&self) -> i32 {
+        self.interval_days
+            .unwrap_or(DEFAULT_REFILL_REMINDER_INTERVAL_DAYS)
+    }
+}
+
+/// Why no reminder was queued for an overdue prescription
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RefillReminderSkipReason {
+    /// Reminded within the interval
+    RecentlyReminded,
+    /// Patient opted out of email notifications
+    EmailDisabled,
+    /// Patient has no email address
+    NoEmail,
+}
+
+/// Overdue prescription no reminder was queued for
+#[derive(Debug, Clone, Serialize)]
+pub struct SkippedRefillReminder {
+    pub prescription_id: Uuid,
+    pub reason: RefillReminderSkipReason,
+}
+
+/// Outcome of POST /api/v1/prescriptions/overdue-refills/reminders
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RefillRemindersResponse {
+    /// Prescriptions a REFILL_REMINDER was queued for
+    pub queued: Vec<Uuid>,
+    pub skipped: Vec<SkippedRefillReminder>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_send_refill_reminders_request_validation() {
+        let request: SendRefillRemindersRequest = serde_json::from_str("{}").unwrap();
+        assert!(request.validate().is_ok());
+        assert!(request.prescription_ids.is_empty());
+        assert_eq!(
+            request.interval_days(),
+            DEFAULT_REFILL_REMINDER_INTERVAL_DAYS
+        );
+
+        let request = SendRefillRemindersRequest {
+            interval_days: Some(0),
+            ..Default::default()
+        };
+        assert!(request.validate().is_err());
+
+        let query = OverdueRefillsQuery {
+            grace_days: -1,
+            ..Default::default()
+        };
+        assert!(query.validate().is_err());
+    }
+}
@@ -0,0 +1,298 @@
+/*!
+ * Preventive Reminder Model
+ *
+ * The notification scheduler queues two kinds of preventive reminders, each
+ * switched on per practice in the notification settings:
+ * - BIRTHDAY_GREETING on the patient's birthday (29 February birthdays are
+ *   greeted on 28 February in other years)
+ * - SCREENING_REMINDER for the screening programs of the
+ *   `screening_programs` setting, e.g. mammography for women aged 50-69
+ *   every 24 months
+ *
+ * A patient is due for a screening program when it applies to their sex and
+ * age, and within the program interval they had neither the screening (an
+ * imaging order of its modality, or a lab order or result for one of its
+ * LOINC codes) nor a reminder for it.
+ */
+
+use chrono::{Datelike, Months, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use validator::{Validate, ValidationError};
+
+use crate::models::ImagingModality;
+
+/// Setting holding the screening programs
+pub const SCREENING_PROGRAMS_SETTING: &str = "screening_programs";
+
+/// Most screening programs per practice
+pub const MAX_SCREENING_PROGRAMS: usize = 20;
+
+/// Longest screening interval, in months
+pub const MAX_SCREENING_INTERVAL_MONTHS: u32 = 120;
+
+/// Most LOINC codes in a program
+pub const MAX_SCREENING_LAB_CODES: usize = 20;
+
+/// A screening program patients are reminded of
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Validate)]
+#[validate(schema(function = "validate_screening_program"))]
+pub struct ScreeningProgram {
+    /// Unique code, recorded in the metadata of the reminders
+    #[validate(length(min = 1, max = 50))]
+    pub code: String,
+    /// Name used in the reminder
+    #[validate(length(min = 1, max = 200))]
+    pub name: String,
+    /// Sex the program applies to ("M" or "F"); every patient when absent
+    pub sex: Option<String>,
+    /// Youngest age the program applies to
+    pub min_age: Option<u32>,
+    /// Oldest age the program applies to
+    pub max_age: Option<u32>,
+    /// Months between screenings
+    pub interval_months: u32,
+    /// Months (1-12) in which to remind, e.g. the flu shot season; any
+    /// month when empty
+    #[serde(default)]
+    pub season_months: Vec<u32>,
+    /// Imaging orders of this modality count as the screening
+    pub imaging_modality: Option<ImagingModality>,
+    /// Lab orders or results for any of these LOINC codes count as the
+    /// screening
+    #[serde(default)]
+    pub lab_loinc_codes: Vec<String>,
+    /// Disabled programs are kept in the setting but not reminded of
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn validate_screening_program(program: &ScreeningProgram) -> Result<(), ValidationError> {
+    let invalid = |code: &'static str, message: String| {
+        let mut error = ValidationError::new(code);
+        error.message = Some(message.into());
+        Err(error)
+    };
+
+    if program
+        .sex
+        .as_deref()
+        .is_some_and(|sex| sex != "M" && sex != "F")
+    {
+        return invalid("invalid_sex", "Sex must be M or F".to_string());
+    }
+    if let (Some(min_age), Some(max_age)) = (program.min_age, program.max_age) {
+        if min_age > max_age {
+            return invalid(
+                "invalid_age_range",
+                "Minimum age must not exceed maximum age".to_string(),
+            );
+        }
+    }
+    if !(1..=MAX_SCREENING_INTERVAL_MONTHS).contains(&program.interval_months) {
+        return invalid(
+            "invalid_interval",
+            format!(
+                "Interval must be 1-{} months",
+                MAX_SCREENING_INTERVAL_MONTHS
+            ),
+        );
+    }
+    if program
+        .season_months
+        .iter()
+        .any(|month| !(1..=12).contains(month))
+    {
+        return invalid("invalid_season", "Season months must be 1-12".to_string());
+    }
+    if program.lab_loinc_codes.len() > MAX_SCREENING_LAB_CODES {
+        return invalid(
+            "too_many_lab_codes",
+            format!("At most {} LOINC codes", MAX_SCREENING_LAB_CODES),
+        );
+    }
+    if program
+        .lab_loinc_codes
+        .iter()
+        .any(|code| code.trim().is_empty() || code.len() > 20)
+    {
+        return invalid("invalid_lab_code", "Invalid LOINC code".to_string());
+    }
+    Ok(())
+}
+
+impl ScreeningProgram {
+    /// Whether the program applies to a patient of this sex (the patient's
+    /// gender) and age, on `today`
+    pub fn applies_to(&self, gender: &str, age: u32, today: NaiveDate) -> bool {
+        self.enabled
+            && self.sex.as_deref().is_none_or(|sex| sex == gender)
+            && self.min_age.is_none_or(|min_age| age >= min_age)
+            && self.max_age.is_none_or(|max_age| age <= max_age)
+            && (self.season_months.is_empty() || self.season_months.contains(&today.month()))
+    }
+
+    /// Start of the interval ending `today`: a screening or reminder since
+    /// then means the patient is not due
+    pub fn since(&self, today: NaiveDate) -> NaiveDate {
+        today
+            .checked_sub_months(Months::new(self.interval_months))
+            .unwrap_or(NaiveDate::MIN)
+    }
+}
+
+/// Parse and validate the value of the `screening_programs` setting
+pub fn parse_screening_programs(
+    value: &serde_json::Value,
+) -> Result<Vec<ScreeningProgram>, String> {
+    let programs: Vec<ScreeningProgram> = serde_json::from_value(value.clone())
+        .map_err(|e| format!("Screening programs must be an array of programs: {}", e))?;
+
+    if programs.len() > MAX_SCREENING_PROGRAMS {
+        return Err(format!(
+            "At most {} screening programs are allowed",
+            MAX_SCREENING_PROGRAMS
+        ));
+    }
+
+    let mut codes = HashSet::new();
+    for program in &programs {
+        program
+            .validate()
+            .map_err(|e| format!("Invalid screening program '{}': {}", program.code, e))?;
+        if !codes.insert(program.code.as_str()) {
+            return Err(format!(
+                "Duplicate screening program code '{}'",
+                program.code
+            ));
+        }
+    }
+
+    Ok(programs)
+}
+
+/// Whether `today` is the birthday of someone born on `date_of_birth`
+///
+/// 29 February birthdays fall on 28 February in non-leap years.
+pub fn is_birthday(date_of_birth: NaiveDate, today: NaiveDate) -> bool {
+    if date_of_birth >= today {
+        return false;
+    }
+    match NaiveDate::from_ymd_opt(today.year(), date_of_birth.month(), date_of_birth.day()) {
+        Some(birthday) => birthday == today,
+        None => today.month() == 2 && today.day() == 28,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn mammography() -> ScreeningProgram {
+        ScreeningProgram {
+            code: "MAMMOGRAPHY".to_string(),
+            name: "Mammography".to_string(),
+            sex: Some("F".to_string()),
+            min_age: Some(50),
+            max_age: Some(69),
+            interval_months: 24,
+            season_months: vec![],
+            imaging_modality: Some(ImagingModality::Mammography),
+            lab_loinc_codes: vec![],
+            enabled: true,
+        }
+    }
+
+    #[test]
+    fn test_applies_to_sex_and_age() {
+        let program = mammography();
+        let today = date(2026, 5, 1);
+        assert!(program.applies_to("F", 50, today));
+        assert!(program.applies_to("F", 69, today));
+        assert!(!program.applies_to("F", 49, today));
+        assert!(!program.applies_to("F", 70, today));
+        assert!(!program.applies_to("M", 55, today));
+
+        let disabled = ScreeningProgram {
+            enabled: false,
+            ..mammography()
+        };
+        assert!(!disabled.applies_to("F", 55, today));
+    }
+
+    #[test]
+    fn test_applies_to_season() {
+        let flu_shot = ScreeningProgram {
+            code: "FLU_SHOT".to_string(),
+            name: "Flu shot".to_string(),
+            sex: None,
+            min_age: Some(65),
+            max_age: None,
+            interval_months: 12,
+            season_months: vec![10, 11],
+            imaging_modality: None,
+            lab_loinc_codes: vec![],
+            enabled: true,
+        };
+        assert!(flu_shot.applies_to("M", 80, date(2026, 10, 15)));
+        assert!(!flu_shot.applies_to("M", 80, date(2026, 12, 1)));
+        assert_eq!(flu_shot.since(date(2026, 10, 15)), date(2025, 10, 15));
+    }
+
+    #[test]
+    fn test_parse_screening_programs() {
+        let value = serde_json::json!([
+            {
+                "code": "MAMMOGRAPHY",
+                "name": "Mammography",
+                "sex": "F",
+                "min_age": 50,
+                "max_age": 69,
+                "interval_months": 24,
+                "imaging_modality": "MAMMOGRAPHY"
+            },
+            {"code": "COLONOSCOPY", "name": "Colonoscopy", "interval_months": 120}
+        ]);
+        let programs = parse_screening_programs(&value).unwrap();
+        assert_eq!(programs.len(), 2);
+        assert_eq!(programs[0], mammography());
+        assert!(programs[1].enabled);
+
+        let duplicate = serde_json::json!([
+            {"code": "A", "name": "A", "interval_months": 12},
+            {"code": "A", "name": "B", "interval_months": 12}
+        ]);
+        assert!(parse_screening_programs(&duplicate).is_err());
+
+        let bad_sex =
+            serde_json::json!([{"code": "A", "name": "A", "sex": "X", "interval_months": 12}]);
+        assert!(parse_screening_programs(&bad_sex).is_err());
+
+        let bad_ages = serde_json::json!([
+            {"code": "A", "name": "A", "min_age": 70, "max_age": 50, "interval_months": 12}
+        ]);
+        assert!(parse_screening_programs(&bad_ages).is_err());
+
+        assert!(parse_screening_programs(&serde_json::json!({"code": "A"})).is_err());
+    }
+
+    #[test]
+    fn test_is_birthday() {
+        assert!(is_birthday(date(1980, 10, 16), date(2026, 10, 16)));
+        assert!(!is_birthday(date(1980, 10, 17), date(2026, 10, 16)));
+        // Not on the day of birth itself
+        assert!(!is_birthday(date(2026, 10, 16), date(2026, 10, 16)));
+        // Leap day birthdays
+        assert!(is_birthday(date(2000, 2, 29), date(2026, 2, 28)));
+        assert!(!is_birthday(date(2000, 2, 29), date(2028, 2, 28)));
+        assert!(is_birthday(date(2000, 2, 29), date(2028, 2, 29)));
+    }
+}
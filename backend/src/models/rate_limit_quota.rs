@@ -0,0 +1,113 @@
+/*!
+ * Rate Limit Quota Model
+ *
+ * Request limits an administrator gave a user in place of the defaults of
+ * the rate limiter (see `middleware::rate_limit`), and the current usage
+ * reported by the admin quota API.
+ */
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::middleware::rate_limit::UserQuota;
+
+/// Quota of a user (rate_limit_quotas row)
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct RateLimitQuota {
+    pub user_id: Uuid,
+    pub requests_per_minute: i32,
+    pub bulk_requests_per_minute: i32,
+    pub reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub updated_by: Option<Uuid>,
+}
+
+impl RateLimitQuota {
+    /// Limits applied by the rate limiter
+    pub fn limits(&self) -> UserQuota {
+        UserQuota {
+            requests_per_minute: self.requests_per_minute.max(1) as u32,
+            bulk_requests_per_minute: self.bulk_requests_per_minute.max(1) as u32,
+        }
+    }
+}
+
+/// Request to set the quota of a user
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct SetRateLimitQuotaRequest {
+    #[validate(range(
+        min = 1,
+        max = 100000,
+        message = "Requests per minute must be between 1 and 100000"
+    ))]
+    pub requests_per_minute: i32,
+
+    #[validate(range(
+        min = 1,
+        max = 10000,
+        message = "Bulk requests per minute must be between 1 and 10000"
+    ))]
+    pub bulk_requests_per_minute: i32,
+
+    #[validate(length(max = 500, message = "Reason must not exceed 500 characters"))]
+    pub reason: Option<String>,
+}
+
+/// Default limits of the rate limiter
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateLimitDefaults {
+    /// Per client IP, before login
+    pub unauthenticated_per_minute: u32,
+    /// Per user
+    pub authenticated_per_minute: u32,
+    /// Bulk, batch, export and import requests, per user or client IP
+    pub bulk_per_minute: u32,
+}
+
+/// Limits and usage of a user in the current window
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserRateLimitStatus {
+    pub user_id: Uuid,
+    /// Quota set by an administrator (None: defaults apply)
+    pub quota: Option<RateLimitQuota>,
+    pub requests_per_minute: u32,
+    pub bulk_requests_per_minute: u32,
+    /// Requests counted in the current window (on this instance, unless the
+    /// counters are shared)
+    pub requests_used: u32,
+    pub bulk_requests_used: u32,
+    /// When the current window ends
+    pub window_resets_at: DateTime<Utc>,
+}
+
+/// Default limits and every user quota
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateLimitOverview {
+    pub defaults: RateLimitDefaults,
+    pub quotas: Vec<UserRateLimitStatus>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_rate_limit_quota_request_validation() {
+        let request = SetRateLimitQuotaRequest {
+            requests_per_minute: 1200,
+            bulk_requests_per_minute: 60,
+            reason: Some("Schedule sync integration".to_string()),
+        };
+        assert!(request.validate().is_ok());
+
+        let request = SetRateLimitQuotaRequest {
+            requests_per_minute: 0,
+            ..request
+        };
+        assert!(request.validate().is_err());
+    }
+}
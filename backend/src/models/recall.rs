@@ -0,0 +1,504 @@
+/*!
+ * Recall Model
+ *
+ * A recall rule finds patients overdue for follow-up: a population (cohort
+ * criteria: age range, diagnoses, active medications) without a lab order or
+ * result for some tests, or without a visit, for a number of months. For
+ * example, diabetics (E11) with no HbA1c (LOINC 4548-4) in 6 months.
+ *
+ * Rules run on a cadence. Each run updates the rule's recall list:
+ * - An overdue patient gets an open entry and is sent reminders, at most
+ *   `max_reminders` of them, `reminder_interval_days` apart.
+ * - An open entry is resolved once its patient is no longer overdue. That
+ *   is the outcome tracked for the campaign.
+ *
+ * Patients can opt out of one rule or of every recall.
+ */
+
+use chrono::{DateTime, Months, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
+
+use crate::models::{CohortCriteria, ReportCadence};
+
+/// Longest overdue interval, in months
+pub const MAX_RECALL_MONTHS: u32 = 60;
+
+/// Most LOINC codes in a rule
+pub const MAX_RECALL_LAB_CODES: usize = 20;
+
+/// Criteria of a recall rule: who is in the population, and when a patient
+/// of the population is overdue
+///
+/// A patient is overdue when every given condition holds: no order or result
+/// for any of `lab_loinc_codes` within `lab_within_months`, and no visit
+/// within `visit_within_months`. Patients who never had a visit do not
+/// match the visit condition.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Validate)]
+#[validate(schema(function = "validate_recall_criteria"))]
+pub struct RecallCriteria {
+    /// Population (`last_visit_before` is not allowed; use
+    /// `visit_within_months`)
+    #[serde(flatten)]
+    #[validate(nested)]
+    pub population: CohortCriteria,
+    /// LOINC codes of the tests the patients are due for (any of them)
+    #[serde(default)]
+    pub lab_loinc_codes: Vec<String>,
+    /// Months within which one of the tests must have been ordered or
+    /// resulted
+    pub lab_within_months: Option<u32>,
+    /// Months within which the patient must have been seen
+    pub visit_within_months: Option<u32>,
+}
+
+impl RecallCriteria {
+    /// Cohort criteria selecting the population, with the visit condition
+    /// as of `today`
+    pub fn cohort_criteria(&self, today: NaiveDate) -> CohortCriteria {
+        CohortCriteria {
+            last_visit_before: self
+                .visit_within_months
+                .map(|months| today - Months::new(months)),
+            ..self.population.clone()
+        }
+    }
+
+    /// Earliest order or result date that satisfies the lab condition, as
+    /// of `today`
+    pub fn lab_cutoff(&self, today: NaiveDate) -> Option<NaiveDate> {
+        if self.lab_loinc_codes.is_empty() {
+            return None;
+        }
+        self.lab_within_months
+            .map(|months| today - Months::new(months))
+    }
+}
+
+/// Validator for the criteria as a whole
+fn validate_recall_criteria(criteria: &RecallCriteria) -> Result<(), ValidationError> {
+    let invalid = |code: &'static str, message: String| {
+        let mut error = ValidationError::new(code);
+        error.message = Some(message.into());
+        Err(error)
+    };
+
+    if criteria.population.last_visit_before.is_some() {
+        return invalid(
+            "invalid_last_visit",
+            "Use visit_within_months instead of last_visit_before".to_string(),
+        );
+    }
+    if criteria.lab_loinc_codes.is_empty() != criteria.lab_within_months.is_none() {
+        return invalid(
+            "invalid_lab_condition",
+            "lab_loinc_codes and lab_within_months must be given together".to_string(),
+        );
+    }
+    if criteria.lab_within_months.is_none() && criteria.visit_within_months.is_none() {
+        return invalid(
+            "missing_condition",
+            "A lab or visit condition is required".to_string(),
+        );
+    }
+    if [criteria.lab_within_months, criteria.visit_within_months]
+        .iter()
+        .flatten()
+        .any(|months| !(1..=MAX_RECALL_MONTHS).contains(months))
+    {
+        return invalid(
+            "invalid_months",
+            format!("Intervals must be 1-{} months", MAX_RECALL_MONTHS),
+        );
+    }
+    if criteria.lab_loinc_codes.len() > MAX_RECALL_LAB_CODES {
+        return invalid(
+            "too_many_lab_codes",
+            format!("At most {} LOINC codes", MAX_RECALL_LAB_CODES),
+        );
+    }
+    if let Some(code) = criteria.lab_loinc_codes.iter().find(|code| {
+        code.is_empty() || code.len() > 10 || !code.chars().all(|c| c.is_ascii_digit() || c == '-')
+    }) {
+        return invalid(
+            "invalid_loinc_code",
+            format!("Invalid LOINC code '{}'", code),
+        );
+    }
+    Ok(())
+}
+
+/// Status of a patient on a recall list
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RecallEntryStatus {
+    /// Overdue; reminders are being sent
+    Open,
+    /// No longer overdue (e.g. the test was ordered)
+    Resolved,
+    /// The patient opted out of the recall
+    OptedOut,
+    /// Closed by staff, or the patient is no longer active
+    Closed,
+}
+
+impl RecallEntryStatus {
+    /// Convert to database string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RecallEntryStatus::Open => "OPEN",
+            RecallEntryStatus::Resolved => "RESOLVED",
+            RecallEntryStatus::OptedOut => "OPTED_OUT",
+            RecallEntryStatus::Closed => "CLOSED",
+        }
+    }
+
+    /// Parse from database string
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "OPEN" => Some(RecallEntryStatus::Open),
+            "RESOLVED" => Some(RecallEntryStatus::Resolved),
+            "OPTED_OUT" => Some(RecallEntryStatus::OptedOut),
+            "CLOSED" => Some(RecallEntryStatus::Closed),
+            _ => None,
+        }
+    }
+}
+
+/// Recall rule row
+#[derive(Debug, Clone, FromRow)]
+pub struct RecallRule {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub criteria: serde_json::Value,
+    pub subject: String,
+    pub message_body: String,
+    pub cadence: String,
+    pub reminder_interval_days: i32,
+    pub max_reminders: i32,
+    pub is_active: bool,
+    pub next_run_at: DateTime<Utc>,
+    pub last_run_at: Option<DateTime<Utc>>,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Recall rule (API output)
+#[derive(Debug, Clone, Serialize)]
+pub struct RecallRuleResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub criteria: RecallCriteria,
+    pub subject: String,
+    pub message_body: String,
+    pub cadence: ReportCadence,
+    pub reminder_interval_days: i32,
+    pub max_reminders: i32,
+    pub is_active: bool,
+    pub next_run_at: DateTime<Utc>,
+    pub last_run_at: Option<DateTime<Utc>>,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl TryFrom<RecallRule> for RecallRuleResponse {
+    type Error = String;
+
+    fn try_from(row: RecallRule) -> Result<Self, Self::Error> {
+        let criteria = serde_json::from_value(row.criteria)
+            .map_err(|e| format!("Invalid recall criteria: {}", e))?;
+        let cadence = ReportCadence::from_str(&row.cadence)
+            .ok_or_else(|| format!("Unknown recall cadence: {}", row.cadence))?;
+
+        Ok(Self {
+            id: row.id,
+            name: row.name,
+            description: row.description,
+            criteria,
+            subject: row.subject,
+            message_body: row.message_body,
+            cadence,
+            reminder_interval_days: row.reminder_interval_days,
+            max_reminders: row.max_reminders,
+            is_active: row.is_active,
+            next_run_at: row.next_run_at,
+            last_run_at: row.last_run_at,
+            created_by: row.created_by,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        })
+    }
+}
+
+/// Request body for POST /api/v1/recalls/rules
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct CreateRecallRuleRequest {
+    #[validate(length(min = 1, max = 200, message = "Name must be 1-200 characters"))]
+    pub name: String,
+    #[validate(length(max = 2000, message = "Description too long (max 2000 chars)"))]
+    pub description: Option<String>,
+    #[validate(nested)]
+    pub criteria: RecallCriteria,
+    #[validate(length(min = 1, max = 255, message = "Subject must be 1-255 characters"))]
+    pub subject: String,
+    #[validate(length(
+        min = 1,
+        max = 10000,
+        message = "Message body must be 1-10000 characters"
+    ))]
+    pub message_body: String,
+    pub cadence: ReportCadence,
+    /// Days between reminders to the same patient (default 30)
+    #[serde(default = "default_reminder_interval_days")]
+    #[validate(range(min = 1, max = 365, message = "Reminder interval must be 1-365 days"))]
+    pub reminder_interval_days: i32,
+    /// Reminders sent per overdue patient (default 3)
+    #[serde(default = "default_max_reminders")]
+    #[validate(range(min = 1, max = 10, message = "Max reminders must be 1-10"))]
+    pub max_reminders: i32,
+}
+
+fn default_reminder_interval_days() -> i32 {
+    30
+}
+
+fn default_max_reminders() -> i32 {
+    3
+}
+
+/// Request body for PUT /api/v1/recalls/rules/:id
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct UpdateRecallRuleRequest {
+    #[validate(length(min = 1, max = 200, message = "Name must be 1-200 characters"))]
+    pub name: Option<String>,
+    #[validate(length(max = 2000, message = "Description too long (max 2000 chars)"))]
+    pub description: Option<String>,
+    #[validate(nested)]
+    pub criteria: Option<RecallCriteria>,
+    #[validate(length(min = 1, max = 255, message = "Subject must be 1-255 characters"))]
+    pub subject: Option<String>,
+    #[validate(length(
+        min = 1,
+        max = 10000,
+        message = "Message body must be 1-10000 characters"
+    ))]
+    pub message_body: Option<String>,
+    /// Changing the cadence reschedules the next run
+    pub cadence: Option<ReportCadence>,
+    #[validate(range(min = 1, max = 365, message = "Reminder interval must be 1-365 days"))]
+    pub reminder_interval_days: Option<i32>,
+    #[validate(range(min = 1, max = 10, message = "Max reminders must be 1-10"))]
+    pub max_reminders: Option<i32>,
+    pub is_active: Option<bool>,
+}
+
+/// One run of a recall rule
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct RecallRun {
+    pub id: Uuid,
+    pub rule_id: Uuid,
+    /// User who ran the rule by hand (None for scheduled runs)
+    pub triggered_by: Option<Uuid>,
+    pub run_at: DateTime<Utc>,
+    /// Patients overdue at this run
+    pub overdue: i32,
+    /// Overdue patients added to the recall list
+    pub new_entries: i32,
+    /// Reminders queued
+    pub reminded: i32,
+    /// Reminders not queued (no email address, email disabled, queue error)
+    pub skipped: i32,
+    /// Overdue patients who opted out
+    pub opted_out: i32,
+    /// Open entries resolved because the patient is no longer overdue
+    pub resolved: i32,
+    /// Patients skipped because their data could not be decrypted
+    pub unreadable_records: i32,
+    /// Why the run failed (nothing else is recorded then)
+    pub error: Option<String>,
+}
+
+/// Recall list entry row
+#[derive(Debug, Clone, FromRow)]
+pub struct RecallEntry {
+    pub id: Uuid,
+    pub rule_id: Uuid,
+    pub patient_id: Uuid,
+    pub status: String,
+    pub reminders_sent: i32,
+    pub last_reminded_at: Option<DateTime<Utc>>,
+    pub last_notification_id: Option<Uuid>,
+    pub last_skip_reason: Option<String>,
+    pub opened_at: DateTime<Utc>,
+    pub closed_at: Option<DateTime<Utc>>,
+}
+
+/// Recall list entry (API output)
+#[derive(Debug, Clone, Serialize)]
+pub struct RecallEntryResponse {
+    pub id: Uuid,
+    pub rule_id: Uuid,
+    pub patient_id: Uuid,
+    pub medical_record_number: String,
+    /// Decrypted name (None if it cannot be decrypted)
+    pub patient_name: Option<String>,
+    pub status: RecallEntryStatus,
+    pub reminders_sent: i32,
+    pub last_reminded_at: Option<DateTime<Utc>>,
+    pub last_notification_id: Option<Uuid>,
+    /// Why the last reminder was not queued
+    pub last_skip_reason: Option<String>,
+    pub opened_at: DateTime<Utc>,
+    /// When the entry was resolved, opted out or closed
+    pub closed_at: Option<DateTime<Utc>>,
+}
+
+/// Query parameters for GET /api/v1/recalls/rules/:id/entries
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RecallEntryFilter {
+    pub status: Option<RecallEntryStatus>,
+}
+
+/// Outcome of a recall campaign
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RecallOutcome {
+    pub rule_id: Uuid,
+    /// Patients ever on the recall list
+    pub total_entries: i64,
+    pub open: i64,
+    pub resolved: i64,
+    pub opted_out: i64,
+    pub closed: i64,
+    /// Reminders queued over all entries
+    pub reminders_sent: i64,
+    /// Open entries that got every reminder without being resolved
+    pub exhausted: i64,
+    /// Resolved entries as a share of the finished ones (percent)
+    pub resolution_rate: Option<f64>,
+    /// Mean days from joining the list to being resolved
+    pub average_days_to_resolution: Option<f64>,
+}
+
+impl RecallOutcome {
+    /// Resolution rate in percent of the entries no longer open
+    pub fn compute_resolution_rate(resolved: i64, finished: i64) -> Option<f64> {
+        (finished > 0).then(|| (resolved as f64 / finished as f64 * 1000.0).round() / 10.0)
+    }
+}
+
+/// Opt-out of a patient from one recall rule or from every recall
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct RecallOptOut {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    /// None: opted out of every recall
+    pub rule_id: Option<Uuid>,
+    pub reason: Option<String>,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Request body for POST /api/v1/recalls/opt-outs
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct CreateRecallOptOutRequest {
+    pub patient_id: Uuid,
+    /// Only this rule (default: every recall)
+    pub rule_id: Option<Uuid>,
+    #[validate(length(max = 500, message = "Reason too long (max 500 chars)"))]
+    pub reason: Option<String>,
+}
+
+/// Query parameters for GET /api/v1/recalls/opt-outs
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RecallOptOutFilter {
+    pub patient_id: Option<Uuid>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_recall_criteria_validation() {
+        let criteria: RecallCriteria = serde_json::from_value(serde_json::json!({
+            "diagnosis_codes": ["E11"],
+            "lab_loinc_codes": ["4548-4"],
+            "lab_within_months": 6
+        }))
+        .unwrap();
+        assert_eq!(criteria.population.diagnosis_codes, vec!["E11".to_string()]);
+        assert!(criteria.validate().is_ok());
+
+        assert!(RecallCriteria::default().validate().is_err());
+
+        let codes_without_months = RecallCriteria {
+            lab_within_months: None,
+            visit_within_months: Some(12),
+            ..criteria.clone()
+        };
+        assert!(codes_without_months.validate().is_err());
+
+        let absolute_date = RecallCriteria {
+            population: CohortCriteria {
+                last_visit_before: Some(date(2026, 1, 1)),
+                ..Default::default()
+            },
+            ..criteria.clone()
+        };
+        assert!(absolute_date.validate().is_err());
+
+        let bad_code = RecallCriteria {
+            lab_loinc_codes: vec!["HbA1c".to_string()],
+            ..criteria
+        };
+        assert!(bad_code.validate().is_err());
+    }
+
+    #[test]
+    fn test_recall_criteria_cutoffs() {
+        let today = date(2026, 8, 31);
+        let criteria = RecallCriteria {
+            population: CohortCriteria {
+                min_age: Some(40),
+                ..Default::default()
+            },
+            lab_loinc_codes: vec!["4548-4".to_string()],
+            lab_within_months: Some(6),
+            visit_within_months: Some(12),
+        };
+
+        assert_eq!(criteria.lab_cutoff(today), Some(date(2026, 2, 28)));
+        let cohort = criteria.cohort_criteria(today);
+        assert_eq!(cohort.last_visit_before, Some(date(2025, 8, 31)));
+        assert_eq!(cohort.min_age, Some(40));
+    }
+
+    #[test]
+    fn test_recall_entry_status_conversion() {
+        for status in [
+            RecallEntryStatus::Open,
+            RecallEntryStatus::Resolved,
+            RecallEntryStatus::OptedOut,
+            RecallEntryStatus::Closed,
+        ] {
+            assert_eq!(RecallEntryStatus::from_str(status.as_str()), Some(status));
+        }
+        assert_eq!(RecallEntryStatus::from_str("PENDING"), None);
+    }
+
+    #[test]
+    fn test_resolution_rate() {
+        assert_eq!(RecallOutcome::compute_resolution_rate(0, 0), None);
+        assert_eq!(RecallOutcome::compute_resolution_rate(2, 3), Some(66.7));
+    }
+}
@@ -0,0 +1,363 @@
+/*!
+ * Referral Model
+ *
+ * Outgoing specialist referrals (specialty, reason, urgency), tracked from
+ * PENDING to SENT until the specialist's response is received. A SENT
+ * referral past its response due date is overdue, and the referring doctor
+ * is reminded by email. The reason, clinical information and response
+ * summary are encrypted.
+ */
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::models::{GeneratedDocumentResponse, TemplateLanguage};
+use crate::utils::encryption::EncryptionKey;
+
+/// Lifecycle of a referral
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ReferralStatus {
+    /// Written, not yet handed to the patient or the specialist
+    Pending,
+    /// Waiting for the specialist's response
+    Sent,
+    Responded,
+    Cancelled,
+}
+
+impl ReferralStatus {
+    /// Convert to database string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReferralStatus::Pending => "PENDING",
+            ReferralStatus::Sent => "SENT",
+            ReferralStatus::Responded => "RESPONDED",
+            ReferralStatus::Cancelled => "CANCELLED",
+        }
+    }
+
+    /// Parse the database string representation
+    pub fn from_db(value: &str) -> Option<Self> {
+        match value {
+            "PENDING" => Some(ReferralStatus::Pending),
+            "SENT" => Some(ReferralStatus::Sent),
+            "RESPONDED" => Some(ReferralStatus::Responded),
+            "CANCELLED" => Some(ReferralStatus::Cancelled),
+            _ => None,
+        }
+    }
+
+    /// Whether a referral can move from this status to `next`
+    ///
+    /// Referrals only move forward (a response can arrive before the
+    /// referral was marked sent); anything without a response can be
+    /// cancelled.
+    pub fn can_transition_to(&self, next: ReferralStatus) -> bool {
+        use ReferralStatus::*;
+        matches!(
+            (self, next),
+            (Pending, Sent)
+                | (Pending, Responded)
+                | (Sent, Responded)
+                | (Pending, Cancelled)
+                | (Sent, Cancelled)
+        )
+    }
+}
+
+/// How soon the patient should be seen by the specialist
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ReferralUrgency {
+    #[default]
+    Routine,
+    Urgent,
+}
+
+impl ReferralUrgency {
+    /// Convert to database string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReferralUrgency::Routine => "ROUTINE",
+            ReferralUrgency::Urgent => "URGENT",
+        }
+    }
+
+    /// Days the specialist response is expected within, when the referral
+    /// does not set a due date
+    pub fn default_response_days(&self) -> i64 {
+        match self {
+            ReferralUrgency::Routine => 30,
+            ReferralUrgency::Urgent => 7,
+        }
+    }
+
+    /// Default response due date for a referral created on `date`
+    pub fn default_due_date(&self, date: NaiveDate) -> NaiveDate {
+        date + Duration::days(self.default_response_days())
+    }
+}
+
+/// Referral row (encrypted)
+#[derive(Debug, Clone, FromRow)]
+pub struct Referral {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub visit_id: Option<Uuid>,
+    pub visit_date: Option<NaiveDate>,
+    pub specialty: String,
+    pub specialist_name: Option<String>,
+    pub specialist_contact: Option<String>,
+    pub reason: String,                // 🔒 Encrypted
+    pub clinical_info: Option<String>, // 🔒 Encrypted
+    pub urgency: String,
+    pub status: String,
+    pub sent_at: Option<DateTime<Utc>>,
+    pub response_due_date: NaiveDate,
+    pub response_received_at: Option<DateTime<Utc>>,
+    pub response_summary: Option<String>, // 🔒 Encrypted
+    pub response_attachment_id: Option<Uuid>,
+    pub referral_document_id: Option<Uuid>,
+    pub last_reminder_at: Option<DateTime<Utc>>,
+    pub reminder_count: i32,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_by: Option<Uuid>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Referral {
+    /// Decrypt into the API representation
+    pub fn decrypt(&self, key: &EncryptionKey) -> Result<ReferralResponse> {
+        let today = Utc::now().date_naive();
+        Ok(ReferralResponse {
+            id: self.id,
+            patient_id: self.patient_id,
+            visit_id: self.visit_id,
+            visit_date: self.visit_date,
+            specialty: self.specialty.clone(),
+            specialist_name: self.specialist_name.clone(),
+            specialist_contact: self.specialist_contact.clone(),
+            reason: key
+                .decrypt(&self.reason)
+                .context("Failed to decrypt referral reason")?,
+            clinical_info: key
+                .decrypt_optional(&self.clinical_info)
+                .context("Failed to decrypt referral clinical_info")?,
+            urgency: self.urgency.clone(),
+            status: self.status.clone(),
+            sent_at: self.sent_at,
+            response_due_date: self.response_due_date,
+            overdue: is_overdue(&self.status, self.response_due_date, today),
+            response_received_at: self.response_received_at,
+            response_summary: key
+                .decrypt_optional(&self.response_summary)
+                .context("Failed to decrypt referral response_summary")?,
+            response_attachment_id: self.response_attachment_id,
+            referral_document_id: self.referral_document_id,
+            last_reminder_at: self.last_reminder_at,
+            reminder_count: self.reminder_count,
+            created_by: self.created_by,
+            created_at: self.created_at,
+            updated_by: self.updated_by,
+            updated_at: self.updated_at,
+        })
+    }
+}
+
+/// Whether a referral is still waiting for a response after its due date
+pub fn is_overdue(status: &str, response_due_date: NaiveDate, today: NaiveDate) -> bool {
+    status == ReferralStatus::Sent.as_str() && response_due_date < today
+}
+
+/// Referral (API output, decrypted)
+#[derive(Debug, Clone, Serialize)]
+pub struct ReferralResponse {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    pub visit_id: Option<Uuid>,
+    pub visit_date: Option<NaiveDate>,
+    pub specialty: String,
+    pub specialist_name: Option<String>,
+    pub specialist_contact: Option<String>,
+    pub reason: String,
+    pub clinical_info: Option<String>,
+    pub urgency: String,
+    pub status: String,
+    pub sent_at: Option<DateTime<Utc>>,
+    pub response_due_date: NaiveDate,
+    /// SENT and past its response due date
+    pub overdue: bool,
+    pub response_received_at: Option<DateTime<Utc>>,
+    pub response_summary: Option<String>,
+    pub response_attachment_id: Option<Uuid>,
+    /// Most recently generated referral letter
+    pub referral_document_id: Option<Uuid>,
+    pub last_reminder_at: Option<DateTime<Utc>>,
+    pub reminder_count: i32,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_by: Option<Uuid>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Request body for POST /api/v1/patients/:id/referrals
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct CreateReferralRequest {
+    /// Visit of the same patient the referral is decided at
+    pub visit_id: Option<Uuid>,
+    #[validate(length(min = 1, max = 100, message = "Specialty must be 1-100 characters"))]
+    pub specialty: String,
+    #[validate(length(max = 255))]
+    pub specialist_name: Option<String>,
+    #[validate(length(max = 255))]
+    pub specialist_contact: Option<String>,
+    #[validate(length(min = 1, max = 5000, message = "Reason must be 1-5000 characters"))]
+    pub reason: String,
+    #[validate(length(max = 5000, message = "Clinical information too long (max 5000 chars)"))]
+    pub clinical_info: Option<String>,
+    #[serde(default)]
+    pub urgency: ReferralUrgency,
+    /// Defaults to 30 days (7 if urgent) from today
+    pub response_due_date: Option<NaiveDate>,
+    /// Mark the referral sent straight away
+    #[serde(default)]
+    pub sent: bool,
+}
+
+/// Request body for PUT /api/v1/patients/:id/referrals/:referral_id
+///
+/// Absent fields are left unchanged. Moving to SENT or RESPONDED stamps
+/// `sent_at`/`response_received_at` with the current time unless given.
+#[derive(Debug, Clone, Default, Deserialize, Validate)]
+pub struct UpdateReferralRequest {
+    pub status: Option<ReferralStatus>,
+    #[validate(length(min = 1, max = 100, message = "Specialty must be 1-100 characters"))]
+    pub specialty: Option<String>,
+    #[validate(length(max = 255))]
+    pub specialist_name: Option<String>,
+    #[validate(length(max = 255))]
+    pub specialist_contact: Option<String>,
+    #[validate(length(min = 1, max = 5000, message = "Reason must be 1-5000 characters"))]
+    pub reason: Option<String>,
+    #[validate(length(max = 5000, message = "Clinical information too long (max 5000 chars)"))]
+    pub clinical_info: Option<String>,
+    pub urgency: Option<ReferralUrgency>,
+    pub response_due_date: Option<NaiveDate>,
+    pub sent_at: Option<DateTime<Utc>>,
+    pub response_received_at: Option<DateTime<Utc>>,
+    #[validate(length(max = 20000, message = "Response summary too long (max 20000 chars)"))]
+    pub response_summary: Option<String>,
+    /// Attachment of the same patient holding the specialist report
+    pub response_attachment_id: Option<Uuid>,
+}
+
+/// Query parameters for GET /api/v1/patients/:id/referrals
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ListReferralsQuery {
+    pub status: Option<ReferralStatus>,
+    pub visit_id: Option<Uuid>,
+    #[serde(default)]
+    pub overdue_only: bool,
+}
+
+/// Query parameters for GET /api/v1/referrals/overdue
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct OverdueReferralsQuery {
+    /// Only referrals made by this doctor
+    pub referred_by: Option<Uuid>,
+}
+
+/// Overdue referral in the practice-wide list (API output)
+#[derive(Debug, Clone, Serialize)]
+pub struct OverdueReferral {
+    pub referral: ReferralResponse,
+    pub patient_name: String,
+    pub days_overdue: i64,
+}
+
+/// Request body for POST /api/v1/patients/:id/referrals/:referral_id/letter
+#[derive(Debug, Clone, Default, Deserialize, Validate)]
+pub struct GenerateReferralLetterRequest {
+    /// REFERRAL_LETTER template to use; defaults to the default one for
+    /// `language`
+    pub template_id: Option<Uuid>,
+    #[serde(default)]
+    pub language: TemplateLanguage,
+    #[validate(length(max = 5000, message = "Clinical history too long (max 5000 chars)"))]
+    pub clinical_history: Option<String>,
+    #[validate(length(max = 5000, message = "Examination findings too long (max 5000 chars)"))]
+    pub examination_findings: Option<String>,
+    #[validate(length(max = 1000, message = "Suspected diagnosis too long (max 1000 chars)"))]
+    pub suspected_diagnosis: Option<String>,
+}
+
+/// Generated referral letter and the referral it was generated for (API output)
+#[derive(Debug, Clone, Serialize)]
+pub struct ReferralLetterResponse {
+    pub referral: ReferralResponse,
+    pub document: GeneratedDocumentResponse,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_transitions() {
+        use ReferralStatus::*;
+        assert!(Pending.can_transition_to(Sent));
+        assert!(Sent.can_transition_to(Responded));
+        assert!(Pending.can_transition_to(Responded));
+        assert!(Sent.can_transition_to(Cancelled));
+
+        assert!(!Responded.can_transition_to(Cancelled));
+        assert!(!Sent.can_transition_to(Pending));
+        assert!(!Cancelled.can_transition_to(Sent));
+        assert!(!Sent.can_transition_to(Sent));
+    }
+
+    #[test]
+    fn test_is_overdue() {
+        let due = NaiveDate::from_ymd_opt(2026, 4, 10).unwrap();
+        let after = NaiveDate::from_ymd_opt(2026, 4, 11).unwrap();
+
+        assert!(is_overdue("SENT", due, after));
+        assert!(!is_overdue("SENT", due, due));
+        assert!(!is_overdue("PENDING", due, after));
+        assert!(!is_overdue("RESPONDED", due, after));
+    }
+
+    #[test]
+    fn test_default_due_date() {
+        let today = NaiveDate::from_ymd_opt(2026, 4, 3).unwrap();
+        assert_eq!(
+            ReferralUrgency::Routine.default_due_date(today),
+            NaiveDate::from_ymd_opt(2026, 5, 3).unwrap()
+        );
+        assert_eq!(
+            ReferralUrgency::Urgent.default_due_date(today),
+            NaiveDate::from_ymd_opt(2026, 4, 10).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_create_referral_request_validation() {
+        let request: CreateReferralRequest = serde_json::from_str(
+            r#"{"specialty": "Cardiologia", "reason": "Palpitazioni ricorrenti"}"#,
+        )
+        .unwrap();
+        assert!(request.validate().is_ok());
+        assert_eq!(request.urgency, ReferralUrgency::Routine);
+        assert!(!request.sent);
+
+        let no_reason: CreateReferralRequest =
+            serde_json::from_str(r#"{"specialty": "Cardiologia", "reason": ""}"#).unwrap();
+        assert!(no_reason.validate().is_err());
+    }
+}
@@ -2,12 +2,14 @@
  * Report Models
  *
  * Data structures for reporting and analytics endpoints.
- * Supports date range filtering for all report types.
+ * Supports date range filtering for all report types, and comparing the
+ * summary metrics of a period with the previous period or the same period
+ * last year.
  */
 
-use chrono::{DateTime, NaiveDate, Utc};
+use chrono::{DateTime, Months, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use uuid::Uuid;
 
 /// Date range filter for reports
@@ -37,6 +39,104 @@ impl DateRangeFilter {
     }
 }
 
+// ========== PERIOD COMPARISON ==========
+
+/// Period the summary metrics of a report are compared with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ComparisonPeriod {
+    /// The period of the same length ending the day before the start date
+    PreviousPeriod,
+    /// The same dates one year earlier
+    SamePeriodLastYear,
+}
+
+impl ComparisonPeriod {
+    /// Date range to compare `range` with
+    pub fn range_for(&self, range: &ReportDateRange) -> ReportDateRange {
+        match self {
+            ComparisonPeriod::PreviousPeriod => {
+                let end_date = range.start_date - chrono::Duration::days(1);
+                ReportDateRange {
+                    start_date: end_date - (range.end_date - range.start_date),
+                    end_date,
+                }
+            }
+            // 29 February falls back to 28 February
+            ComparisonPeriod::SamePeriodLastYear => ReportDateRange {
+                start_date: range.start_date - Months::new(12),
+                end_date: range.end_date - Months::new(12),
+            },
+        }
+    }
+}
+
+/// Change of a summary metric from the comparison period
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct MetricChange {
+    pub current: f64,
+    pub previous: f64,
+    /// current - previous (percentage points for rates)
+    pub delta: f64,
+    /// Relative change in percent; None when the previous value is zero
+    pub percent_change: Option<f64>,
+}
+
+impl MetricChange {
+    pub fn new(current: f64, previous: f64) -> Self {
+        let percent_change = if previous != 0.0 {
+            Some((current - previous) / previous.abs() * 100.0)
+        } else {
+            None
+        };
+
+        Self {
+            current,
+            previous,
+            delta: current - previous,
+            percent_change,
+        }
+    }
+}
+
+/// Summary metrics of a report compared with another period
+#[derive(Debug, Clone, Serialize)]
+pub struct PeriodComparison {
+    pub compare_to: ComparisonPeriod,
+    /// Date range of the comparison period
+    pub date_range: ReportDateRange,
+    /// Changes by metric name (the report field of the metric)
+    pub metrics: BTreeMap<&'static str, MetricChange>,
+}
+
+impl PeriodComparison {
+    /// Compare the summary metrics of two reports, pairing them by name
+    pub fn new(
+        compare_to: ComparisonPeriod,
+        date_range: ReportDateRange,
+        current: &[(&'static str, f64)],
+        previous: &[(&'static str, f64)],
+    ) -> Self {
+        let metrics = current
+            .iter()
+            .filter_map(|(name, value)| {
+                previous
+                    .iter()
+                    .find(|(previous_name, _)| previous_name == name)
+                    .map(|(_, previous_value)| {
+                        (*name, MetricChange::new(*value, *previous_value))
+                    })
+            })
+            .collect();
+
+        Self {
+            compare_to,
+            date_range,
+            metrics,
+        }
+    }
+}
+
 // ========== APPOINTMENT REPORTS ==========
 
 /// Appointment utilization report query parameters
@@ -48,6 +148,10 @@ pub struct AppointmentReportFilter {
     pub end_date: Option<NaiveDate>,
     /// Provider ID filter (optional)
     pub provider_id: Option<Uuid>,
+    /// Compute from live data instead of the report views (default false)
+    pub live: Option<bool>,
+    /// Compare the summary metrics with another period (requires a date range)
+    pub compare_to: Option<ComparisonPeriod>,
 }
 
 /// Appointment utilization report response
@@ -79,6 +183,26 @@ pub struct AppointmentUtilizationReport {
     pub by_hour: Vec<HourlyCount>,
     /// Daily trend data
     pub daily_trend: Vec<DailyAppointmentCount>,
+    /// When the report views it was read from were refreshed (None: computed live)
+    pub data_as_of: Option<DateTime<Utc>>,
+    /// Summary metrics compared with the `compare_to` period
+    pub comparison: Option<PeriodComparison>,
+}
+
+impl AppointmentUtilizationReport {
+    /// Summary metrics compared between periods
+    pub fn summary_metrics(&self) -> Vec<(&'static str, f64)> {
+        vec![
+            ("total_scheduled", self.total_scheduled as f64),
+            ("completed", self.completed as f64),
+            ("cancelled", self.cancelled as f64),
+            ("no_shows", self.no_shows as f64),
+            ("utilization_rate", self.utilization_rate),
+            ("no_show_rate", self.no_show_rate),
+            ("cancellation_rate", self.cancellation_rate),
+            ("avg_appointments_per_day", self.avg_appointments_per_day),
+        ]
+    }
 }
 
 /// Date range included in report response
@@ -118,6 +242,42 @@ pub struct DailyAppointmentCount {
     pub no_shows: i64,
 }
 
+/// Appointment heatmap response (weekday x hour matrix)
+///
+/// Matrices are indexed `[day][hour]`, with day 0=Sunday .. 6=Saturday and
+/// hour 0-23. Cancelled appointments are excluded since they do not occupy
+/// capacity.
+#[derive(Debug, Clone, Serialize)]
+pub struct AppointmentHeatmapReport {
+    /// Report date range
+    pub date_range: ReportDateRange,
+    /// Day names for the matrix rows
+    pub days: Vec<String>,
+    /// Booked (non-cancelled) appointments starting in each slot
+    pub bookings: Vec<Vec<i64>>,
+    /// No-shows in each slot
+    pub no_shows: Vec<Vec<i64>>,
+    /// Average bookings per occurrence of the weekday in the period
+    pub booking_density: Vec<Vec<f64>>,
+    /// Average no-shows per occurrence of the weekday in the period
+    pub no_show_density: Vec<Vec<f64>>,
+    /// Busiest slot's booking count (for color scaling)
+    pub max_bookings: i64,
+    pub total_bookings: i64,
+    pub total_no_shows: i64,
+}
+
+/// Bookings and no-shows for one weekday/hour slot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::FromRow)]
+pub struct HeatmapSlotCount {
+    /// Day index (0=Sunday, 1=Monday, etc.)
+    pub day: i32,
+    /// Hour (0-23)
+    pub hour: i32,
+    pub bookings: i64,
+    pub no_shows: i64,
+}
+
 // ========== PATIENT REPORTS ==========
 
 /// Patient statistics report query parameters
@@ -127,6 +287,10 @@ pub struct PatientReportFilter {
     pub start_date: Option<NaiveDate>,
     /// End date for new patient registration (optional)
     pub end_date: Option<NaiveDate>,
+    /// Compute from live data instead of the report views (default false)
+    pub live: Option<bool>,
+    /// Compare the summary metrics with another period (requires a date range)
+    pub compare_to: Option<ComparisonPeriod>,
 }
 
 /// Patient statistics report response
@@ -152,6 +316,22 @@ pub struct PatientStatisticsReport {
     pub age_distribution: Vec<AgeGroupCount>,
     /// Monthly registration trend (last 12 months)
     pub monthly_registrations: Vec<MonthlyCount>,
+    /// When the report views it was read from were refreshed (None: computed live)
+    pub data_as_of: Option<DateTime<Utc>>,
+    /// Summary metrics compared with the `compare_to` period
+    pub comparison: Option<PeriodComparison>,
+}
+
+impl PatientStatisticsReport {
+    /// Summary metrics compared between periods
+    ///
+    /// Only registrations depend on the period; the patient totals are
+    /// current counts.
+    pub fn summary_metrics(&self) -> Vec<(&'static str, f64)> {
+        self.new_patients_in_period
+            .map(|count| vec![("new_patients_in_period", count as f64)])
+            .unwrap_or_default()
+    }
 }
 
 /// Gender breakdown statistics
@@ -170,6 +350,10 @@ pub struct AgeGroupCount {
     pub age_group: String,
     /// Count of patients in group
     pub count: i64,
+    /// Male patients in group (age pyramid)
+    pub male: i64,
+    /// Female patients in group (age pyramid)
+    pub female: i64,
 }
 
 /// Monthly count for trends
@@ -198,6 +382,10 @@ pub struct DiagnosisReportFilter {
     pub provider_id: Option<Uuid>,
     /// Limit number of top diagnoses
     pub limit: Option<i32>,
+    /// Compute from live data instead of the report views (default false)
+    pub live: Option<bool>,
+    /// Compare the summary metrics with another period (requires a date range)
+    pub compare_to: Option<ComparisonPeriod>,
 }
 
 /// Diagnosis trends report response
@@ -215,6 +403,20 @@ pub struct DiagnosisTrendsReport {
     pub monthly_trend: Vec<MonthlyDiagnosisCount>,
     /// Diagnoses by category (ICD-10 chapter)
     pub by_category: Vec<DiagnosisCategoryCount>,
+    /// When the report views it was read from were refreshed (None: computed live)
+    pub data_as_of: Option<DateTime<Utc>>,
+    /// Summary metrics compared with the `compare_to` period
+    pub comparison: Option<PeriodComparison>,
+}
+
+impl DiagnosisTrendsReport {
+    /// Summary metrics compared between periods
+    pub fn summary_metrics(&self) -> Vec<(&'static str, f64)> {
+        vec![
+            ("total_diagnoses", self.total_diagnoses as f64),
+            ("unique_codes", self.unique_codes as f64),
+        ]
+    }
 }
 
 /// Diagnosis frequency count
@@ -261,6 +463,8 @@ pub struct ProductivityReportFilter {
     pub end_date: Option<NaiveDate>,
     /// Provider ID filter (optional, if not specified returns all providers)
     pub provider_id: Option<Uuid>,
+    /// Compare the summary metrics with another period (requires a date range)
+    pub compare_to: Option<ComparisonPeriod>,
 }
 
 /// Provider productivity report response
@@ -272,6 +476,23 @@ pub struct ProviderProductivityReport {
     pub summary: ProductivitySummary,
     /// Per-provider breakdown (if multiple providers)
     pub by_provider: Vec<ProviderProductivity>,
+    /// Summary metrics compared with the `compare_to` period
+    pub comparison: Option<PeriodComparison>,
+}
+
+impl ProviderProductivityReport {
+    /// Summary metrics compared between periods
+    pub fn summary_metrics(&self) -> Vec<(&'static str, f64)> {
+        vec![
+            ("total_appointments", self.summary.total_appointments as f64),
+            ("completed_appointments", self.summary.completed_appointments as f64),
+            ("total_visits", self.summary.total_visits as f64),
+            ("total_prescriptions", self.summary.total_prescriptions as f64),
+            ("total_documents", self.summary.total_documents as f64),
+            ("avg_appointment_duration", self.summary.avg_appointment_duration),
+            ("avg_wait_minutes", self.summary.avg_wait_minutes),
+        ]
+    }
 }
 
 /// Productivity summary
@@ -289,6 +510,10 @@ pub struct ProductivitySummary {
     pub total_documents: i64,
     /// Average appointment duration (minutes)
     pub avg_appointment_duration: f64,
+    /// Average wait from arrival to the start of the visit (minutes)
+    pub avg_wait_minutes: f64,
+    /// Median wait from arrival to the start of the visit (minutes)
+    pub median_wait_minutes: f64,
 }
 
 /// Individual provider productivity
@@ -314,6 +539,8 @@ pub struct ProviderProductivity {
     pub avg_visits_per_day: f64,
     /// Completion rate
     pub completion_rate: f64,
+    /// Average wait from arrival to the start of the visit (minutes)
+    pub avg_wait_minutes: f64,
 }
 
 // ========== REVENUE REPORTS (Optional) ==========
@@ -327,6 +554,8 @@ pub struct RevenueReportFilter {
     pub end_date: Option<NaiveDate>,
     /// Provider ID filter (optional)
     pub provider_id: Option<Uuid>,
+    /// Compare the summary metrics with another period (requires a date range)
+    pub compare_to: Option<ComparisonPeriod>,
 }
 
 /// Revenue report response (placeholder - can be expanded based on billing integration)
@@ -342,6 +571,18 @@ pub struct RevenueReport {
     pub avg_visits_per_day: f64,
     /// Note: Actual revenue tracking requires billing module integration
     pub note: String,
+    /// Summary metrics compared with the `compare_to` period
+    pub comparison: Option<PeriodComparison>,
+}
+
+impl RevenueReport {
+    /// Summary metrics compared between periods
+    pub fn summary_metrics(&self) -> Vec<(&'static str, f64)> {
+        vec![
+            ("total_visits", self.total_visits as f64),
+            ("avg_visits_per_day", self.avg_visits_per_day),
+        ]
+    }
 }
 
 // ========== COMBINED DASHBOARD REPORT ==========
@@ -355,6 +596,8 @@ pub struct DashboardReport {
     pub quick_stats: QuickStats,
     /// Recent activity
     pub recent_activity: RecentActivity,
+    /// Post-visit survey results
+    pub satisfaction: SatisfactionSummary,
 }
 
 /// Quick statistics for dashboard
@@ -376,6 +619,30 @@ pub struct QuickStats {
     pub documents_this_month: i64,
 }
 
+/// Post-visit survey results over the last `period_days` days, by the day
+/// the survey was sent
+#[derive(Debug, Clone, Serialize)]
+pub struct SatisfactionSummary {
+    /// Days covered, up to today
+    pub period_days: i64,
+    /// Surveys sent
+    pub surveys_sent: i64,
+    /// Surveys answered
+    pub responses: i64,
+    /// Percentage of surveys answered
+    pub response_rate: f64,
+    /// Average score (0-10); absent without responses
+    pub average_score: Option<f64>,
+    /// Responses scoring 9 or 10
+    pub promoters: i64,
+    /// Responses scoring 7 or 8
+    pub passives: i64,
+    /// Responses scoring 0 to 6
+    pub detractors: i64,
+    /// Net Promoter Score (-100 to 100); absent without responses
+    pub nps: Option<f64>,
+}
+
 /// Recent activity summary
 #[derive(Debug, Clone, Serialize)]
 pub struct RecentActivity {
@@ -415,10 +682,47 @@ pub struct NewPatientSummary {
     pub registered_at: DateTime<Utc>,
 }
 
+// ========== GP QUALITY INDICATORS ==========
+
+/// Quality indicator report query parameters
+#[derive(Debug, Clone, Deserialize)]
+pub struct QualityIndicatorFilter {
+    /// Start of the measurement period (default: 12 months before end_date)
+    pub start_date: Option<NaiveDate>,
+    /// End of the measurement period (default: today)
+    pub end_date: Option<NaiveDate>,
+}
+
+/// One governance indicator (numerator / denominator)
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct QualityIndicator {
+    /// Indicator code used in the regional CSV layout
+    pub code: String,
+    pub description: String,
+    pub numerator: i64,
+    pub denominator: i64,
+    /// Numerator as a percentage of the denominator (0 when the denominator is 0)
+    pub percentage: f64,
+}
+
+/// GP quality indicators report
+///
+/// Every computation is recorded in `quality_indicator_runs`; `run_id`
+/// identifies the stored inputs and results.
+#[derive(Debug, Clone, Serialize)]
+pub struct QualityIndicatorReport {
+    pub run_id: Uuid,
+    pub date_range: ReportDateRange,
+    pub generated_at: DateTime<Utc>,
+    pub indicators: Vec<QualityIndicator>,
+    /// Criteria and population counts the indicators were computed from
+    pub inputs: serde_json::Value,
+}
+
 // ========== EXPORT FORMATS ==========
 
 /// Export format options
-#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum ExportFormat {
     Json,
@@ -427,6 +731,29 @@ pub enum ExportFormat {
     Excel,
 }
 
+impl ExportFormat {
+    /// Convert to database string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Pdf => "pdf",
+            ExportFormat::Excel => "excel",
+        }
+    }
+
+    /// Parse from database string
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "json" => Some(ExportFormat::Json),
+            "csv" => Some(ExportFormat::Csv),
+            "pdf" => Some(ExportFormat::Pdf),
+            "excel" => Some(ExportFormat::Excel),
+            _ => None,
+        }
+    }
+}
+
 impl Default for ExportFormat {
     fn default() -> Self {
         Self::Json
@@ -434,7 +761,7 @@ impl Default for ExportFormat {
 }
 
 /// Report type for export
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum ReportType {
     AppointmentUtilization,
@@ -445,6 +772,45 @@ pub enum ReportType {
     Dashboard,
 }
 
+impl ReportType {
+    /// Convert to database string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReportType::AppointmentUtilization => "appointment_utilization",
+            ReportType::PatientStatistics => "patient_statistics",
+            ReportType::DiagnosisTrends => "diagnosis_trends",
+            ReportType::ProviderProductivity => "provider_productivity",
+            ReportType::Revenue => "revenue",
+            ReportType::Dashboard => "dashboard",
+        }
+    }
+
+    /// Parse from database string
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "appointment_utilization" => Some(ReportType::AppointmentUtilization),
+            "patient_statistics" => Some(ReportType::PatientStatistics),
+            "diagnosis_trends" => Some(ReportType::DiagnosisTrends),
+            "provider_productivity" => Some(ReportType::ProviderProductivity),
+            "revenue" => Some(ReportType::Revenue),
+            "dashboard" => Some(ReportType::Dashboard),
+            _ => None,
+        }
+    }
+
+    /// Human-readable name (email subjects)
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            ReportType::AppointmentUtilization => "Appointment Utilization Report",
+            ReportType::PatientStatistics => "Patient Statistics Report",
+            ReportType::DiagnosisTrends => "Diagnosis Trends Report",
+            ReportType::ProviderProductivity => "Provider Productivity Report",
+            ReportType::Revenue => "Revenue Report",
+            ReportType::Dashboard => "Dashboard Report",
+        }
+    }
+}
+
 /// Export request
 #[derive(Debug, Clone, Deserialize)]
 pub struct ExportReportRequest {
@@ -458,6 +824,10 @@ pub struct ExportReportRequest {
     pub end_date: Option<NaiveDate>,
     /// Provider filter
     pub provider_id: Option<Uuid>,
+    /// Compute from live data instead of the report views (default false)
+    pub live: Option<bool>,
+    /// Compare the summary metrics with another period (JSON exports)
+    pub compare_to: Option<ComparisonPeriod>,
 }
 
 #[cfg(test)]
@@ -483,4 +853,53 @@ mod tests {
     fn test_export_format_default() {
         assert_eq!(ExportFormat::default(), ExportFormat::Json);
     }
+
+    fn range(start: (i32, u32, u32), end: (i32, u32, u32)) -> ReportDateRange {
+        ReportDateRange {
+            start_date: NaiveDate::from_ymd_opt(start.0, start.1, start.2).unwrap(),
+            end_date: NaiveDate::from_ymd_opt(end.0, end.1, end.2).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_comparison_period_ranges() {
+        let march = range((2024, 3, 1), (2024, 3, 31));
+
+        let previous = ComparisonPeriod::PreviousPeriod.range_for(&march);
+        assert_eq!(previous.start_date, NaiveDate::from_ymd_opt(2024, 1, 30).unwrap());
+        assert_eq!(previous.end_date, NaiveDate::from_ymd_opt(2024, 2, 29).unwrap());
+
+        let last_year = ComparisonPeriod::SamePeriodLastYear.range_for(&march);
+        assert_eq!(last_year.start_date, NaiveDate::from_ymd_opt(2023, 3, 1).unwrap());
+        assert_eq!(last_year.end_date, NaiveDate::from_ymd_opt(2023, 3, 31).unwrap());
+
+        let leap_day = range((2024, 2, 1), (2024, 2, 29));
+        let last_year = ComparisonPeriod::SamePeriodLastYear.range_for(&leap_day);
+        assert_eq!(last_year.end_date, NaiveDate::from_ymd_opt(2023, 2, 28).unwrap());
+    }
+
+    #[test]
+    fn test_metric_change() {
+        let change = MetricChange::new(120.0, 100.0);
+        assert_eq!(change.delta, 20.0);
+        assert_eq!(change.percent_change, Some(20.0));
+
+        let from_zero = MetricChange::new(5.0, 0.0);
+        assert_eq!(from_zero.delta, 5.0);
+        assert_eq!(from_zero.percent_change, None);
+    }
+
+    #[test]
+    fn test_period_comparison_pairs_metrics() {
+        let comparison = PeriodComparison::new(
+            ComparisonPeriod::PreviousPeriod,
+            range((2024, 2, 1), (2024, 2, 29)),
+            &[("total_visits", 30.0), ("avg_visits_per_day", 1.0)],
+            &[("total_visits", 40.0)],
+        );
+
+        assert_eq!(comparison.metrics.len(), 1);
+        assert_eq!(comparison.metrics["total_visits"], MetricChange::new(30.0, 40.0));
+        assert_eq!(comparison.metrics["total_visits"].percent_change, Some(-25.0));
+    }
 }
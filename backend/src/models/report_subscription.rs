@@ -0,0 +1,367 @@
+/*!
+ * Report Subscription Model
+ *
+ * A report subscription emails a report to a list of recipients on a
+ * cadence: weekly, monthly or quarterly. Each delivery covers the last
+ * complete period (the previous week, month or quarter) and is rendered
+ * with the permissions of the user who created the subscription.
+ */
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::{Validate, ValidateEmail, ValidationError};
+
+use crate::models::{ExportFormat, ReportDateRange, ReportType};
+
+/// Hour of the day (UTC) deliveries are sent, once their period is over
+pub const DELIVERY_HOUR_UTC: u32 = 6;
+
+/// Most recipients of a subscription
+pub const MAX_RECIPIENTS: usize = 20;
+
+/// How often a subscribed report is sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ReportCadence {
+    /// Every Monday, covering the previous week (Monday to Sunday)
+    Weekly,
+    /// On the 1st of every month, covering the previous month
+    Monthly,
+    /// On the 1st of January, April, July and October, covering the previous quarter
+    Quarterly,
+}
+
+impl ReportCadence {
+    /// Convert to database string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReportCadence::Weekly => "WEEKLY",
+            ReportCadence::Monthly => "MONTHLY",
+            ReportCadence::Quarterly => "QUARTERLY",
+        }
+    }
+
+    /// Parse from database string
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "WEEKLY" => Some(ReportCadence::Weekly),
+            "MONTHLY" => Some(ReportCadence::Monthly),
+            "QUARTERLY" => Some(ReportCadence::Quarterly),
+            _ => None,
+        }
+    }
+
+    /// First day of the period containing `date`
+    fn period_start(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            ReportCadence::Weekly => {
+                date - Duration::days(date.weekday().num_days_from_monday() as i64)
+            }
+            ReportCadence::Monthly => date.with_day(1).unwrap_or(date),
+            ReportCadence::Quarterly => {
+                let month = (date.month0() / 3) * 3 + 1;
+                NaiveDate::from_ymd_opt(date.year(), month, 1).unwrap_or(date)
+            }
+        }
+    }
+
+    /// First day of the period after the one starting on `start`
+    fn next_period_start(&self, start: NaiveDate) -> NaiveDate {
+        match self {
+            ReportCadence::Weekly => start + Duration::days(7),
+            ReportCadence::Monthly => start + chrono::Months::new(1),
+            ReportCadence::Quarterly => start + chrono::Months::new(3),
+        }
+    }
+
+    /// Last complete period before the one containing `date`
+    pub fn previous_period(&self, date: NaiveDate) -> ReportDateRange {
+        let end_date = self.period_start(date) - Duration::days(1);
+        ReportDateRange {
+            start_date: self.period_start(end_date),
+            end_date,
+        }
+    }
+
+    /// When the next delivery after `now` is due: the start of the next
+    /// period, at DELIVERY_HOUR_UTC
+    pub fn next_run_after(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let today = now.date_naive();
+        let start = self.period_start(today);
+        let mut next = self.next_period_start(start);
+        // Today's delivery is still ahead when today starts a period
+        if start == today && now.time() < delivery_time() {
+            next = today;
+        }
+        next.and_time(delivery_time()).and_utc()
+    }
+}
+
+fn delivery_time() -> chrono::NaiveTime {
+    chrono::NaiveTime::from_hms_opt(DELIVERY_HOUR_UTC, 0, 0).unwrap_or_default()
+}
+
+/// Outcome of the last delivery of a subscription
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ReportDeliveryStatus {
+    /// Sent to every recipient
+    Sent,
+    /// Sent to some recipients only
+    PartiallySent,
+    /// Not sent (report could not be generated or no email was accepted)
+    Failed,
+}
+
+impl ReportDeliveryStatus {
+    /// Convert to database string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReportDeliveryStatus::Sent => "SENT",
+            ReportDeliveryStatus::PartiallySent => "PARTIALLY_SENT",
+            ReportDeliveryStatus::Failed => "FAILED",
+        }
+    }
+
+    /// Parse from database string
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "SENT" => Some(ReportDeliveryStatus::Sent),
+            "PARTIALLY_SENT" => Some(ReportDeliveryStatus::PartiallySent),
+            "FAILED" => Some(ReportDeliveryStatus::Failed),
+            _ => None,
+        }
+    }
+}
+
+/// Filters applied to a subscribed report (stored as JSONB)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReportSubscriptionFilters {
+    /// Only this provider (appointment, diagnosis, productivity and revenue reports)
+    pub provider_id: Option<Uuid>,
+}
+
+/// Report subscription row
+#[derive(Debug, Clone, FromRow)]
+pub struct ReportSubscription {
+    pub id: Uuid,
+    pub name: String,
+    pub report_type: String,
+    pub filters: serde_json::Value,
+    pub format: String,
+    pub cadence: String,
+    pub recipients: Vec<String>,
+    pub is_active: bool,
+    pub next_run_at: DateTime<Utc>,
+    pub last_run_at: Option<DateTime<Utc>>,
+    pub last_status: Option<String>,
+    pub last_error: Option<String>,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Report subscription (API output)
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportSubscriptionResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub report_type: ReportType,
+    pub filters: ReportSubscriptionFilters,
+    pub format: ExportFormat,
+    pub cadence: ReportCadence,
+    pub recipients: Vec<String>,
+    pub is_active: bool,
+    pub next_run_at: DateTime<Utc>,
+    pub last_run_at: Option<DateTime<Utc>>,
+    pub last_status: Option<ReportDeliveryStatus>,
+    pub last_error: Option<String>,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl TryFrom<ReportSubscription> for ReportSubscriptionResponse {
+    type Error = String;
+
+    fn try_from(row: ReportSubscription) -> Result<Self, Self::Error> {
+        let report_type = ReportType::from_str(&row.report_type)
+            .ok_or_else(|| format!("Unknown report type: {}", row.report_type))?;
+        let format = ExportFormat::from_str(&row.format)
+            .ok_or_else(|| format!("Unknown export format: {}", row.format))?;
+        let cadence = ReportCadence::from_str(&row.cadence)
+            .ok_or_else(|| format!("Unknown report cadence: {}", row.cadence))?;
+        let filters = serde_json::from_value(row.filters)
+            .map_err(|e| format!("Invalid report subscription filters: {}", e))?;
+
+        Ok(Self {
+            id: row.id,
+            name: row.name,
+            report_type,
+            filters,
+            format,
+            cadence,
+            recipients: row.recipients,
+            is_active: row.is_active,
+            next_run_at: row.next_run_at,
+            last_run_at: row.last_run_at,
+            last_status: row
+                .last_status
+                .as_deref()
+                .and_then(ReportDeliveryStatus::from_str),
+            last_error: row.last_error,
+            created_by: row.created_by,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        })
+    }
+}
+
+/// Validator for the recipient list: 1 to MAX_RECIPIENTS email addresses
+fn validate_recipients(recipients: &[String]) -> Result<(), ValidationError> {
+    if recipients.is_empty() || recipients.len() > MAX_RECIPIENTS {
+        let mut error = ValidationError::new("invalid_recipients");
+        error.message =
+            Some(format!("Between 1 and {} recipients required", MAX_RECIPIENTS).into());
+        return Err(error);
+    }
+    if let Some(invalid) = recipients.iter().find(|email| !email.validate_email()) {
+        let mut error = ValidationError::new("invalid_recipient_email");
+        error.message = Some(format!("Invalid recipient email '{}'", invalid).into());
+        return Err(error);
+    }
+    Ok(())
+}
+
+/// Request body for POST /api/v1/reports/subscriptions
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct CreateReportSubscriptionRequest {
+    #[validate(length(min = 1, max = 200, message = "Name must be 1-200 characters"))]
+    pub name: String,
+    pub report_type: ReportType,
+    #[serde(default)]
+    pub filters: ReportSubscriptionFilters,
+    /// Attachment format (default pdf)
+    #[serde(default = "default_format")]
+    pub format: ExportFormat,
+    pub cadence: ReportCadence,
+    #[validate(custom(function = "validate_recipients"))]
+    pub recipients: Vec<String>,
+}
+
+fn default_format() -> ExportFormat {
+    ExportFormat::Pdf
+}
+
+/// Request body for PUT /api/v1/reports/subscriptions/:id
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct UpdateReportSubscriptionRequest {
+    #[validate(length(min = 1, max = 200, message = "Name must be 1-200 characters"))]
+    pub name: Option<String>,
+    pub filters: Option<ReportSubscriptionFilters>,
+    pub format: Option<ExportFormat>,
+    /// Changing the cadence reschedules the next delivery
+    pub cadence: Option<ReportCadence>,
+    #[validate(custom(function = "validate_recipients"))]
+    pub recipients: Option<Vec<String>>,
+    pub is_active: Option<bool>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_report_cadence_conversion() {
+        for cadence in [
+            ReportCadence::Weekly,
+            ReportCadence::Monthly,
+            ReportCadence::Quarterly,
+        ] {
+            assert_eq!(ReportCadence::from_str(cadence.as_str()), Some(cadence));
+        }
+        assert_eq!(ReportCadence::from_str("DAILY"), None);
+    }
+
+    #[test]
+    fn test_previous_period() {
+        // Wednesday 2026-03-18
+        let today = date(2026, 3, 18);
+        let week = ReportCadence::Weekly.previous_period(today);
+        assert_eq!(
+            (week.start_date, week.end_date),
+            (date(2026, 3, 9), date(2026, 3, 15))
+        );
+
+        let month = ReportCadence::Monthly.previous_period(today);
+        assert_eq!(
+            (month.start_date, month.end_date),
+            (date(2026, 2, 1), date(2026, 2, 28))
+        );
+
+        let quarter = ReportCadence::Quarterly.previous_period(today);
+        assert_eq!(
+            (quarter.start_date, quarter.end_date),
+            (date(2025, 10, 1), date(2025, 12, 31))
+        );
+    }
+
+    #[test]
+    fn test_next_run_after() {
+        let at = |y, m, d, h| Utc.with_ymd_and_hms(y, m, d, h, 0, 0).unwrap();
+
+        assert_eq!(
+            ReportCadence::Monthly.next_run_after(at(2026, 3, 18, 12)),
+            at(2026, 4, 1, 6)
+        );
+        // Before the delivery hour on the 1st, today's delivery is next
+        assert_eq!(
+            ReportCadence::Monthly.next_run_after(at(2026, 4, 1, 3)),
+            at(2026, 4, 1, 6)
+        );
+        assert_eq!(
+            ReportCadence::Monthly.next_run_after(at(2026, 4, 1, 6)),
+            at(2026, 5, 1, 6)
+        );
+        assert_eq!(
+            ReportCadence::Weekly.next_run_after(at(2026, 3, 18, 12)),
+            at(2026, 3, 23, 6)
+        );
+        assert_eq!(
+            ReportCadence::Quarterly.next_run_after(at(2026, 11, 30, 12)),
+            at(2027, 1, 1, 6)
+        );
+    }
+
+    #[test]
+    fn test_create_report_subscription_request_validation() {
+        let request: CreateReportSubscriptionRequest = serde_json::from_value(serde_json::json!({
+            "name": "Monthly productivity",
+            "report_type": "provider_productivity",
+            "cadence": "MONTHLY",
+            "recipients": ["owner@example.com"]
+        }))
+        .unwrap();
+        assert_eq!(request.format, ExportFormat::Pdf);
+        assert!(request.validate().is_ok());
+
+        let no_recipients = CreateReportSubscriptionRequest {
+            recipients: vec![],
+            ..request.clone()
+        };
+        assert!(no_recipients.validate().is_err());
+
+        let bad_email = CreateReportSubscriptionRequest {
+            recipients: vec!["not-an-email".to_string()],
+            ..request
+        };
+        assert!(bad_email.validate().is_err());
+    }
+}
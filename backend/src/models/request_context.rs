@@ -4,16 +4,29 @@
  * Holds HTTP request metadata (IP address, user agent, request ID) that is
  * extracted from incoming requests via middleware and passed through to
  * services for audit logging.
+ *
+ * The request ID doubles as the correlation ID: it is accepted from an inbound
+ * `X-Request-Id` header, echoed back on the response, and kept in a task-local
+ * so services can stamp it on rows that are processed later by background jobs.
  */
 
+use std::future::Future;
 use uuid::Uuid;
 
+/// Header carrying the correlation ID (inbound and outbound)
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+tokio::task_local! {
+    /// Correlation ID of the request (or background job) currently executing
+    static CURRENT_REQUEST_ID: Uuid;
+}
+
 /// Request context containing metadata about the HTTP request.
 /// This is populated by the request_context_middleware and can be
 /// passed to services that need to create audit log entries.
 #[derive(Clone, Debug)]
 pub struct RequestContext {
-    /// Client IP address (from X-Forwarded-For, X-Real-IP, or direct connection)
+    /// Client IP address (connection peer, or forwarded hop behind trusted proxies)
     pub ip_address: Option<String>,
     /// Client User-Agent header
     pub user_agent: Option<String>,
@@ -24,19 +37,83 @@ pub struct RequestContext {
 impl RequestContext {
     /// Create a new RequestContext with the provided values
     pub fn new(ip_address: Option<String>, user_agent: Option<String>) -> Self {
+        Self::with_request_id(ip_address, user_agent, Uuid::new_v4())
+    }
+
+    /// Create a RequestContext reusing an existing correlation ID
+    pub fn with_request_id(
+        ip_address: Option<String>,
+        user_agent: Option<String>,
+        request_id: Uuid,
+    ) -> Self {
         Self {
             ip_address,
             user_agent,
-            request_id: Uuid::new_v4(),
+            request_id,
         }
     }
 
     /// Create an empty context (for cases where no request context is available)
+    ///
+    /// Inherits the current correlation ID when called inside a request scope.
     pub fn empty() -> Self {
         Self {
             ip_address: None,
             user_agent: None,
-            request_id: Uuid::new_v4(),
+            request_id: current_request_id().unwrap_or_else(Uuid::new_v4),
         }
     }
 }
+
+/// Parse an inbound correlation ID header value
+///
+/// Only UUIDs are accepted so clients cannot inject arbitrary strings into
+/// logs and audit rows; anything else is replaced by a fresh ID.
+pub fn parse_request_id(value: Option<&str>) -> Option<Uuid> {
+    value.and_then(|v| Uuid::parse_str(v.trim()).ok())
+}
+
+/// Correlation ID of the current task, if running inside a request or job scope
+pub fn current_request_id() -> Option<Uuid> {
+    CURRENT_REQUEST_ID.try_with(|id| *id).ok()
+}
+
+/// Run a future with the given correlation ID in scope
+///
+/// Used by the request context middleware and by background jobs that resume
+/// work originally triggered by a request.
+pub async fn with_request_id_scope<F>(request_id: Uuid, future: F) -> F::Output
+where
+    F: Future,
+{
+    CURRENT_REQUEST_ID.scope(request_id, future).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_request_id() {
+        let id = Uuid::new_v4();
+        assert_eq!(parse_request_id(Some(&id.to_string())), Some(id));
+        assert_eq!(parse_request_id(Some(&format!(" {} ", id))), Some(id));
+        assert_eq!(parse_request_id(Some("not-a-uuid")), None);
+        assert_eq!(parse_request_id(Some("abc\r\nInjected: header")), None);
+        assert_eq!(parse_request_id(None), None);
+    }
+
+    #[tokio::test]
+    async fn test_request_id_scope() {
+        assert_eq!(current_request_id(), None);
+
+        let id = Uuid::new_v4();
+        let inner = with_request_id_scope(id, async {
+            (current_request_id(), RequestContext::empty().request_id)
+        })
+        .await;
+        assert_eq!(inner, (Some(id), id));
+
+        assert_eq!(current_request_id(), None);
+    }
+}
@@ -0,0 +1,261 @@
+/*!
+ * Research Dataset Export Model
+ *
+ * De-identified, k-anonymized datasets for research or teaching. Records
+ * carry no direct identifiers; the quasi-identifiers (age, gender, region,
+ * period) are generalized according to an anonymization profile until every
+ * combination is shared by at least `k` patients, and records that still
+ * stand out are suppressed.
+ */
+
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::Validate;
+
+use super::{ExportFormat, ReportDateRange};
+
+/// Default smallest equivalence class (patients sharing the same quasi-identifiers)
+pub const DEFAULT_RESEARCH_EXPORT_MIN_K: i64 = 5;
+
+/// Value written in place of a generalized-away or unknown quasi-identifier
+pub const SUPPRESSED_VALUE: &str = "*";
+
+/// Dataset layout
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResearchDatasetType {
+    /// One row per patient seen in the period (visit count, diagnosis groups)
+    Patients,
+    /// One row per diagnosis recorded in the period
+    Diagnoses,
+}
+
+impl ResearchDatasetType {
+    /// Column names, quasi-identifiers first
+    pub fn columns(&self) -> Vec<&'static str> {
+        match self {
+            ResearchDatasetType::Patients => {
+                vec!["age_band", "gender", "region", "visits", "diagnosis_groups"]
+            }
+            ResearchDatasetType::Diagnoses => vec![
+                "age_band",
+                "gender",
+                "region",
+                "period",
+                "icd10",
+                "diagnosis_type",
+            ],
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ResearchDatasetType::Patients => "patients",
+            ResearchDatasetType::Diagnoses => "diagnoses",
+        }
+    }
+}
+
+/// Granularity of event dates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DatePrecision {
+    Year,
+    Quarter,
+    Month,
+}
+
+/// Granularity of ICD-10 codes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Icd10Precision {
+    /// Three-character category (e.g. `E11`)
+    Category,
+    /// Full code as recorded (e.g. `E11.9`)
+    Full,
+}
+
+/// How a research dataset is de-identified
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[serde(default)]
+pub struct AnonymizationProfile {
+    /// Smallest equivalence class allowed (at least `research_export_min_k`)
+    #[validate(range(min = 2, max = 100, message = "k must be 2-100"))]
+    pub k: i64,
+    /// Width of the age bands
+    #[validate(range(min = 1, max = 50, message = "Age bands must be 1-50 years"))]
+    pub age_band_years: u32,
+    /// Ages from here on are reported as a single open band (e.g. `90+`)
+    #[validate(range(min = 50, max = 120, message = "Top age must be 50-120"))]
+    pub top_age: u32,
+    pub date_precision: DatePrecision,
+    pub icd10_precision: Icd10Precision,
+    /// Report the province of residence
+    pub include_region: bool,
+    /// Report the gender
+    pub include_gender: bool,
+}
+
+impl Default for AnonymizationProfile {
+    fn default() -> Self {
+        Self {
+            k: DEFAULT_RESEARCH_EXPORT_MIN_K,
+            age_band_years: 10,
+            top_age: 90,
+            date_precision: DatePrecision::Quarter,
+            icd10_precision: Icd10Precision::Category,
+            include_region: true,
+            include_gender: true,
+        }
+    }
+}
+
+impl AnonymizationProfile {
+    /// Age band label, e.g. `40-49` or `90+`
+    pub fn age_band(&self, age: Option<u32>) -> String {
+        let Some(age) = age else {
+            return SUPPRESSED_VALUE.to_string();
+        };
+        if age >= self.top_age {
+            return format!("{}+", self.top_age);
+        }
+        let width = self.age_band_years.max(1);
+        let low = age / width * width;
+        let high = (low + width - 1).min(self.top_age - 1);
+        format!("{}-{}", low, high)
+    }
+
+    /// Period label, e.g. `2026`, `2026-Q1` or `2026-03`
+    pub fn period(&self, date: NaiveDate) -> String {
+        match self.date_precision {
+            DatePrecision::Year => date.year().to_string(),
+            DatePrecision::Quarter => format!("{}-Q{}", date.year(), (date.month() - 1) / 3 + 1),
+            DatePrecision::Month => format!("{}-{:02}", date.year(), date.month()),
+        }
+    }
+
+    /// ICD-10 code at the profile's precision
+    pub fn icd10(&self, code: &str) -> String {
+        let code = code.trim().to_uppercase();
+        match self.icd10_precision {
+            Icd10Precision::Category => code.replace('.', "").chars().take(3).collect(),
+            Icd10Precision::Full => code,
+        }
+    }
+}
+
+/// Request body for POST /api/v1/reports/research-export
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct ResearchExportRequest {
+    pub dataset: ResearchDatasetType,
+    /// `csv` (default), `excel` or `json`
+    #[serde(default = "default_research_format")]
+    pub format: ExportFormat,
+    /// Period (defaults to the last 12 months)
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    #[serde(default)]
+    #[validate(nested)]
+    pub profile: AnonymizationProfile,
+}
+
+fn default_research_format() -> ExportFormat {
+    ExportFormat::Csv
+}
+
+/// A de-identified record before k-anonymization
+///
+/// `subject` is only used to count distinct patients per equivalence class
+/// and is never exported.
+#[derive(Debug, Clone)]
+pub struct ResearchRecord {
+    pub subject: Uuid,
+    pub age: Option<u32>,
+    pub gender: String,
+    pub region: Option<String>,
+    pub event_date: Option<NaiveDate>,
+    /// Non-identifying attributes, in column order after the quasi-identifiers
+    pub values: Vec<String>,
+}
+
+/// What anonymization did to the dataset
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AnonymizationSummary {
+    /// Records before anonymization
+    pub source_records: usize,
+    pub exported_records: usize,
+    /// Records whose region or period was generalized to reach k
+    pub generalized_records: usize,
+    /// Records dropped because they could not reach k
+    pub suppressed_records: usize,
+    pub equivalence_classes: usize,
+    /// Patients in the smallest exported class (0 if nothing was exported)
+    pub smallest_class: usize,
+    /// Patients skipped because their data could not be decrypted
+    pub unreadable_records: usize,
+}
+
+/// k-anonymized dataset ready for export
+#[derive(Debug, Clone, Serialize)]
+pub struct AnonymizedDataset {
+    pub dataset: ResearchDatasetType,
+    pub date_range: ReportDateRange,
+    pub generated_at: chrono::DateTime<chrono::Utc>,
+    pub profile: AnonymizationProfile,
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+    pub summary: AnonymizationSummary,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_age_bands() {
+        let profile = AnonymizationProfile::default();
+        assert_eq!(profile.age_band(Some(0)), "0-9");
+        assert_eq!(profile.age_band(Some(47)), "40-49");
+        assert_eq!(profile.age_band(Some(89)), "80-89");
+        assert_eq!(profile.age_band(Some(90)), "90+");
+        assert_eq!(profile.age_band(Some(104)), "90+");
+        assert_eq!(profile.age_band(None), SUPPRESSED_VALUE);
+
+        let narrow = AnonymizationProfile {
+            age_band_years: 15,
+            top_age: 85,
+            ..Default::default()
+        };
+        assert_eq!(narrow.age_band(Some(80)), "75-84");
+    }
+
+    #[test]
+    fn test_period_and_icd10_precision() {
+        let date = NaiveDate::from_ymd_opt(2026, 5, 14).unwrap();
+        let mut profile = AnonymizationProfile::default();
+        assert_eq!(profile.period(date), "2026-Q2");
+        profile.date_precision = DatePrecision::Month;
+        assert_eq!(profile.period(date), "2026-05");
+        profile.date_precision = DatePrecision::Year;
+        assert_eq!(profile.period(date), "2026");
+
+        assert_eq!(profile.icd10("e11.9"), "E11");
+        profile.icd10_precision = Icd10Precision::Full;
+        assert_eq!(profile.icd10("e11.9"), "E11.9");
+    }
+
+    #[test]
+    fn test_research_export_request_defaults() {
+        let request: ResearchExportRequest =
+            serde_json::from_str(r#"{"dataset": "diagnoses", "profile": {"k": 10}}"#).unwrap();
+        assert_eq!(request.format, ExportFormat::Csv);
+        assert_eq!(request.profile.k, 10);
+        assert_eq!(request.profile.age_band_years, 10);
+        assert!(request.validate().is_ok());
+
+        let too_small: ResearchExportRequest =
+            serde_json::from_str(r#"{"dataset": "patients", "profile": {"k": 1}}"#).unwrap();
+        assert!(too_small.validate().is_err());
+    }
+}
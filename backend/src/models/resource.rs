@@ -0,0 +1,224 @@
+/*!
+ * Resource Models
+ *
+ * Rooms and equipment (an ECG machine, an ultrasound scanner) that
+ * appointments reserve for their whole time. A resource is booked by one
+ * appointment at a time, whichever the provider: creating or moving an
+ * appointment checks the calendars of its resources as well as the
+ * provider's, and the availability check reports which resource is booked
+ * when the provider is free.
+ *
+ * Resources are deactivated rather than deleted, so that past appointments
+ * keep their reservations; inactive resources cannot be booked.
+ */
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+use super::TimeSlot;
+
+/// Kind of resource
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "VARCHAR", rename_all = "SCREAMING_SNAKE_CASE")]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ResourceType {
+    /// Consulting or treatment room
+    Room,
+    /// Device such as an ECG machine or an ultrasound scanner
+    Equipment,
+}
+
+/// Bookable resource (API output)
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct Resource {
+    pub id: Uuid,
+    pub name: String,
+    pub resource_type: ResourceType,
+    pub description: Option<String>,
+    pub is_active: bool,
+    pub created_by: Option<Uuid>,
+    pub updated_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Request body for POST /api/v1/appointments/resources
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CreateResourceRequest {
+    #[validate(length(
+        min = 1,
+        max = 100,
+        message = "Name must be between 1 and 100 characters"
+    ))]
+    pub name: String,
+
+    pub resource_type: ResourceType,
+
+    #[validate(length(max = 1000, message = "Description must not exceed 1000 characters"))]
+    pub description: Option<String>,
+}
+
+/// Request body for PUT /api/v1/appointments/resources/:id
+///
+/// Fields left out are kept; `is_active: false` deactivates the resource.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct UpdateResourceRequest {
+    #[validate(length(
+        min = 1,
+        max = 100,
+        message = "Name must be between 1 and 100 characters"
+    ))]
+    pub name: Option<String>,
+
+    pub resource_type: Option<ResourceType>,
+
+    #[validate(length(max = 1000, message = "Description must not exceed 1000 characters"))]
+    pub description: Option<String>,
+
+    pub is_active: Option<bool>,
+}
+
+/// Query parameters for GET /api/v1/appointments/resources
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ResourceQuery {
+    /// Include deactivated resources
+    #[serde(default)]
+    pub include_inactive: bool,
+}
+
+/// Reservation of a resource by another appointment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromRow)]
+pub struct ResourceBooking {
+    pub resource_id: Uuid,
+    pub appointment_id: Uuid,
+    pub scheduled_start: DateTime<Utc>,
+    pub scheduled_end: DateTime<Utc>,
+}
+
+/// Mark the slots during which requested resources are booked
+///
+/// Each slot lists the resources booked during it and becomes unavailable
+/// if any is. Returns the bottleneck: the resource booked in the most slots
+/// that were free otherwise (on a tie, the first in `resource_ids`).
+pub fn mark_busy_resources(
+    slots: &mut [TimeSlot],
+    resource_ids: &[Uuid],
+    bookings: &[ResourceBooking],
+) -> Option<Uuid> {
+    let mut blocked_slots: HashMap<Uuid, usize> = HashMap::new();
+
+    for slot in slots.iter_mut() {
+        for booking in bookings {
+            if booking.scheduled_start < slot.end
+                && booking.scheduled_end > slot.start
+                && !slot.busy_resources.contains(&booking.resource_id)
+            {
+                slot.busy_resources.push(booking.resource_id);
+            }
+        }
+        if slot.available && !slot.busy_resources.is_empty() {
+            for resource_id in &slot.busy_resources {
+                *blocked_slots.entry(*resource_id).or_default() += 1;
+            }
+            slot.available = false;
+        }
+    }
+
+    resource_ids
+        .iter()
+        .filter_map(|id| blocked_slots.get(id).map(|count| (*id, *count)))
+        .fold(
+            None,
+            |best: Option<(Uuid, usize)>, (id, count)| match best {
+                Some((_, best_count)) if best_count >= count => best,
+                _ => Some((id, count)),
+            },
+        )
+        .map(|(id, _)| id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 5, 11, hour, minute, 0).unwrap()
+    }
+
+    fn slot(hour: u32, minute: u32) -> TimeSlot {
+        TimeSlot {
+            start: at(hour, minute),
+            end: at(hour, minute) + Duration::minutes(30),
+            available: true,
+            busy_resources: Vec::new(),
+        }
+    }
+
+    fn booking(resource_id: Uuid, start: DateTime<Utc>, end: DateTime<Utc>) -> ResourceBooking {
+        ResourceBooking {
+            resource_id,
+            appointment_id: Uuid::new_v4(),
+            scheduled_start: start,
+            scheduled_end: end,
+        }
+    }
+
+    #[test]
+    fn test_mark_busy_resources() {
+        let room = Uuid::new_v4();
+        let ecg = Uuid::new_v4();
+        let mut slots = vec![slot(8, 0), slot(8, 30), slot(9, 0)];
+
+        let bottleneck = mark_busy_resources(
+            &mut slots,
+            &[room, ecg],
+            &[
+                booking(room, at(8, 15), at(8, 45)),
+                booking(ecg, at(8, 30), at(9, 0)),
+            ],
+        );
+
+        assert_eq!(slots[0].busy_resources, vec![room]);
+        assert_eq!(slots[1].busy_resources, vec![room, ecg]);
+        assert!(slots[2].busy_resources.is_empty());
+        assert!(!slots[0].available && !slots[1].available);
+        assert!(slots[2].available);
+        assert_eq!(bottleneck, Some(room));
+    }
+
+    #[test]
+    fn test_bottleneck_counts_slots_free_otherwise() {
+        let room = Uuid::new_v4();
+        let ecg = Uuid::new_v4();
+        let bookings = [
+            booking(room, at(8, 0), at(8, 30)),
+            booking(ecg, at(8, 30), at(9, 30)),
+        ];
+
+        let mut slots = vec![slot(8, 0), slot(8, 30), slot(9, 0)];
+        assert_eq!(mark_busy_resources(&mut slots, &[room, ecg], &[]), None);
+        assert_eq!(
+            mark_busy_resources(&mut slots, &[room, ecg], &bookings),
+            Some(ecg)
+        );
+
+        // The provider is busy at 9:00, so the ECG only blocks one slot
+        let mut slots = vec![slot(8, 0), slot(8, 30), slot(9, 0)];
+        slots[2].available = false;
+        assert_eq!(
+            mark_busy_resources(&mut slots, &[room, ecg], &bookings),
+            Some(room)
+        );
+        let mut slots = vec![slot(8, 0), slot(8, 30)];
+        assert_eq!(
+            mark_busy_resources(&mut slots, &[ecg, room], &bookings),
+            Some(ecg)
+        );
+    }
+}
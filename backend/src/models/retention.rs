@@ -0,0 +1,286 @@
+/*!
+ * Data Retention Model
+ *
+ * Retention rules say how long records of each entity type are kept before
+ * the retention job purges them. A rule in dry-run mode only reports what
+ * would be purged. Every execution is recorded as a retention run.
+ */
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+/// Shortest retention any rule accepts
+pub const MIN_RETENTION_DAYS: i32 = 30;
+
+/// Shortest audit log retention (6 years, as required by HIPAA)
+pub const MIN_AUDIT_LOG_RETENTION_DAYS: i32 = 2190;
+
+/// Entity type a retention rule applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RetentionEntityType {
+    /// Generated document rows and their PDFs
+    GeneratedDocuments,
+    /// Monthly audit log partitions
+    AuditLogs,
+    /// Sent, failed and cancelled notifications
+    Notifications,
+    /// Finished GDPR export records
+    PatientDataExports,
+    /// Patients, visits and prescriptions in the recycle bin
+    RecycleBin,
+}
+
+impl RetentionEntityType {
+    /// Convert to database string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RetentionEntityType::GeneratedDocuments => "GENERATED_DOCUMENTS",
+            RetentionEntityType::AuditLogs => "AUDIT_LOGS",
+            RetentionEntityType::Notifications => "NOTIFICATIONS",
+            RetentionEntityType::PatientDataExports => "PATIENT_DATA_EXPORTS",
+            RetentionEntityType::RecycleBin => "RECYCLE_BIN",
+        }
+    }
+
+    /// Parse from database string
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "GENERATED_DOCUMENTS" => Some(RetentionEntityType::GeneratedDocuments),
+            "AUDIT_LOGS" => Some(RetentionEntityType::AuditLogs),
+            "NOTIFICATIONS" => Some(RetentionEntityType::Notifications),
+            "PATIENT_DATA_EXPORTS" => Some(RetentionEntityType::PatientDataExports),
+            "RECYCLE_BIN" => Some(RetentionEntityType::RecycleBin),
+            _ => None,
+        }
+    }
+
+    /// Shortest retention accepted for this entity type
+    pub fn min_retention_days(&self) -> i32 {
+        match self {
+            RetentionEntityType::AuditLogs => MIN_AUDIT_LOG_RETENTION_DAYS,
+            _ => MIN_RETENTION_DAYS,
+        }
+    }
+}
+
+/// Status of a retention run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RetentionRunStatus {
+    Completed,
+    Failed,
+}
+
+impl RetentionRunStatus {
+    /// Convert to database string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RetentionRunStatus::Completed => "COMPLETED",
+            RetentionRunStatus::Failed => "FAILED",
+        }
+    }
+
+    /// Parse from database string
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "COMPLETED" => Some(RetentionRunStatus::Completed),
+            "FAILED" => Some(RetentionRunStatus::Failed),
+            _ => None,
+        }
+    }
+}
+
+/// Retention rule row
+#[derive(Debug, Clone, FromRow)]
+pub struct RetentionRule {
+    pub id: Uuid,
+    pub entity_type: String,
+    pub retention_days: i32,
+    pub is_enabled: bool,
+    pub dry_run: bool,
+    pub description: Option<String>,
+    pub last_run_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub updated_by: Option<Uuid>,
+}
+
+impl RetentionRule {
+    /// Records created before this instant are past retention
+    pub fn cutoff(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        now - Duration::days(self.retention_days as i64)
+    }
+}
+
+/// Retention rule (API output)
+#[derive(Debug, Clone, Serialize)]
+pub struct RetentionRuleResponse {
+    pub id: Uuid,
+    pub entity_type: RetentionEntityType,
+    pub retention_days: i32,
+    pub is_enabled: bool,
+    pub dry_run: bool,
+    pub description: Option<String>,
+    pub last_run_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub updated_by: Option<Uuid>,
+}
+
+impl TryFrom<RetentionRule> for RetentionRuleResponse {
+    type Error = String;
+
+    fn try_from(rule: RetentionRule) -> Result<Self, Self::Error> {
+        let entity_type = RetentionEntityType::from_str(&rule.entity_type)
+            .ok_or_else(|| format!("Unknown retention entity type: {}", rule.entity_type))?;
+
+        Ok(Self {
+            id: rule.id,
+            entity_type,
+            retention_days: rule.retention_days,
+            is_enabled: rule.is_enabled,
+            dry_run: rule.dry_run,
+            description: rule.description,
+            last_run_at: rule.last_run_at,
+            created_at: rule.created_at,
+            updated_at: rule.updated_at,
+            updated_by: rule.updated_by,
+        })
+    }
+}
+
+/// Request body for PUT /api/v1/retention-rules/:id
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct UpdateRetentionRuleRequest {
+    #[validate(range(min = 30, max = 36500, message = "Retention must be 30-36500 days"))]
+    pub retention_days: Option<i32>,
+    pub is_enabled: Option<bool>,
+    pub dry_run: Option<bool>,
+}
+
+/// Request body for POST /api/v1/retention-rules/run
+#[derive(Debug, Clone, Deserialize)]
+pub struct RunRetentionRequest {
+    /// Only count what would be purged (default true)
+    #[serde(default = "default_true")]
+    pub dry_run: bool,
+    /// Run a single rule instead of every enabled one
+    pub entity_type: Option<RetentionEntityType>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Retention run row
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct RetentionRun {
+    pub id: Uuid,
+    pub rule_id: Uuid,
+    pub entity_type: String,
+    pub dry_run: bool,
+    pub cutoff: DateTime<Utc>,
+    pub eligible_count: i64,
+    pub purged_count: i64,
+    pub status: String,
+    pub error_message: Option<String>,
+    pub triggered_by: Option<Uuid>,
+    pub started_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+/// Query parameters for GET /api/v1/retention-rules/runs
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ListRetentionRunsQuery {
+    pub entity_type: Option<RetentionEntityType>,
+    pub dry_run: Option<bool>,
+    /// Maximum runs returned (default 50, max 500)
+    pub limit: Option<i64>,
+}
+
+impl ListRetentionRunsQuery {
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(50).clamp(1, 500)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(entity_type: RetentionEntityType, retention_days: i32) -> RetentionRule {
+        let now = Utc::now();
+        RetentionRule {
+            id: Uuid::new_v4(),
+            entity_type: entity_type.as_str().to_string(),
+            retention_days,
+            is_enabled: true,
+            dry_run: true,
+            description: None,
+            last_run_at: None,
+            created_at: now,
+            updated_at: now,
+            updated_by: None,
+        }
+    }
+
+    #[test]
+    fn test_retention_entity_type_conversion() {
+        for entity_type in [
+            RetentionEntityType::GeneratedDocuments,
+            RetentionEntityType::AuditLogs,
+            RetentionEntityType::Notifications,
+            RetentionEntityType::PatientDataExports,
+            RetentionEntityType::RecycleBin,
+        ] {
+            assert_eq!(
+                RetentionEntityType::from_str(entity_type.as_str()),
+                Some(entity_type)
+            );
+        }
+        assert_eq!(RetentionEntityType::from_str("VISITS"), None);
+    }
+
+    #[test]
+    fn test_retention_minimums() {
+        assert_eq!(
+            RetentionEntityType::AuditLogs.min_retention_days(),
+            MIN_AUDIT_LOG_RETENTION_DAYS
+        );
+        assert_eq!(
+            RetentionEntityType::Notifications.min_retention_days(),
+            MIN_RETENTION_DAYS
+        );
+
+        let too_short = UpdateRetentionRuleRequest {
+            retention_days: Some(7),
+            is_enabled: None,
+            dry_run: None,
+        };
+        assert!(too_short.validate().is_err());
+    }
+
+    #[test]
+    fn test_retention_rule_cutoff() {
+        let now = Utc::now();
+        let rule = rule(RetentionEntityType::GeneratedDocuments, 3650);
+        assert_eq!(rule.cutoff(now), now - Duration::days(3650));
+
+        let response = RetentionRuleResponse::try_from(rule).unwrap();
+        assert_eq!(
+            response.entity_type,
+            RetentionEntityType::GeneratedDocuments
+        );
+    }
+
+    #[test]
+    fn test_run_retention_request_defaults_to_dry_run() {
+        let request: RunRetentionRequest = serde_json::from_str("{}").unwrap();
+        assert!(request.dry_run);
+        assert_eq!(request.entity_type, None);
+    }
+}
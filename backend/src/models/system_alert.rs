@@ -0,0 +1,192 @@
+/*!
+ * System Alert Model
+ *
+ * Internal operational alerts raised by background tasks (e.g. the
+ * stuck-state janitor) so that problems which have no user-facing request
+ * are still visible to administrators.
+ */
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+/// Alert severity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum AlertSeverity {
+    Info,
+    Warning,
+    Critical,
+}
+
+impl AlertSeverity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AlertSeverity::Info => "INFO",
+            AlertSeverity::Warning => "WARNING",
+            AlertSeverity::Critical => "CRITICAL",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_uppercase().as_str() {
+            "INFO" => Some(AlertSeverity::Info),
+            "WARNING" => Some(AlertSeverity::Warning),
+            "CRITICAL" => Some(AlertSeverity::Critical),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for AlertSeverity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// System alert row
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct SystemAlert {
+    pub id: Uuid,
+    pub alert_type: String,
+    pub severity: String,
+    pub source: String,
+    pub entity_type: Option<String>,
+    pub entity_id: Option<Uuid>,
+    pub message: String,
+    pub details: Option<serde_json::Value>,
+    pub created_at: DateTime<Utc>,
+    pub acknowledged_at: Option<DateTime<Utc>>,
+    pub acknowledged_by: Option<Uuid>,
+}
+
+/// New alert to record
+#[derive(Debug, Clone)]
+pub struct CreateSystemAlert {
+    pub alert_type: &'static str,
+    pub severity: AlertSeverity,
+    pub source: &'static str,
+    pub entity_type: Option<&'static str>,
+    pub entity_id: Option<Uuid>,
+    pub message: String,
+    pub details: Option<serde_json::Value>,
+}
+
+/// Query parameters for listing alerts
+#[derive(Debug, Clone, Deserialize)]
+pub struct ListSystemAlertsQuery {
+    /// Include acknowledged alerts (default: only open alerts)
+    #[serde(default)]
+    pub include_acknowledged: bool,
+    /// Maximum number of alerts to return (default 50, max 200)
+    pub limit: Option<i64>,
+}
+
+impl SystemAlert {
+    /// Record a new alert
+    pub async fn create(pool: &PgPool, alert: CreateSystemAlert) -> Result<Uuid, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            INSERT INTO system_alerts (
+                alert_type, severity, source, entity_type, entity_id, message, details
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING id
+            "#,
+        )
+        .bind(alert.alert_type)
+        .bind(alert.severity.as_str())
+        .bind(alert.source)
+        .bind(alert.entity_type)
+        .bind(alert.entity_id)
+        .bind(alert.message)
+        .bind(alert.details)
+        .fetch_one(pool)
+        .await
+    }
+
+    /// List alerts, most recent first
+    pub async fn list(
+        pool: &PgPool,
+        include_acknowledged: bool,
+        limit: i64,
+    ) -> Result<Vec<SystemAlert>, sqlx::Error> {
+        sqlx::query_as::<_, SystemAlert>(
+            r#"
+            SELECT id, alert_type, severity, source, entity_type, entity_id,
+                   message, details, created_at, acknowledged_at, acknowledged_by
+            FROM system_alerts
+            WHERE $1 OR acknowledged_at IS NULL
+            ORDER BY created_at DESC
+            LIMIT $2
+            "#,
+        )
+        .bind(include_acknowledged)
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Mark an alert as acknowledged; returns None if it does not exist
+    pub async fn acknowledge(
+        pool: &PgPool,
+        id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Option<SystemAlert>, sqlx::Error> {
+        sqlx::query_as::<_, SystemAlert>(
+            r#"
+            UPDATE system_alerts
+            SET acknowledged_at = COALESCE(acknowledged_at, NOW()),
+                acknowledged_by = COALESCE(acknowledged_by, $2)
+            WHERE id = $1
+            RETURNING id, alert_type, severity, source, entity_type, entity_id,
+                      message, details, created_at, acknowledged_at, acknowledged_by
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Count open (unacknowledged) alerts per severity
+    pub async fn count_open(pool: &PgPool) -> Result<Vec<(String, i64)>, sqlx::Error> {
+        sqlx::query_as::<_, (String, i64)>(
+            r#"
+            SELECT severity, COUNT(*)
+            FROM system_alerts
+            WHERE acknowledged_at IS NULL
+            GROUP BY severity
+            "#,
+        )
+        .fetch_all(pool)
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alert_severity_roundtrip() {
+        for severity in [
+            AlertSeverity::Info,
+            AlertSeverity::Warning,
+            AlertSeverity::Critical,
+        ] {
+            assert_eq!(AlertSeverity::from_str(severity.as_str()), Some(severity));
+        }
+        assert_eq!(AlertSeverity::from_str("warning"), Some(AlertSeverity::Warning));
+        assert_eq!(AlertSeverity::from_str("ERROR"), None);
+    }
+
+    #[test]
+    fn test_alert_severity_serialization() {
+        assert_eq!(
+            serde_json::to_value(AlertSeverity::Critical).unwrap(),
+            serde_json::json!("CRITICAL")
+        );
+    }
+}
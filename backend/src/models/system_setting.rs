@@ -82,6 +82,7 @@ pub enum SettingGroup {
     Security,
     Backup,
     Localization,
+    Branding,
     System,
 }
 
@@ -95,6 +96,7 @@ impl SettingGroup {
             SettingGroup::Security => "security",
             SettingGroup::Backup => "backup",
             SettingGroup::Localization => "localization",
+            SettingGroup::Branding => "branding",
             SettingGroup::System => "system",
         }
     }
@@ -108,6 +110,7 @@ impl SettingGroup {
             "security" => Some(SettingGroup::Security),
             "backup" => Some(SettingGroup::Backup),
             "localization" => Some(SettingGroup::Localization),
+            "branding" => Some(SettingGroup::Branding),
             "system" => Some(SettingGroup::System),
             _ => None,
         }
@@ -122,6 +125,7 @@ impl SettingGroup {
             SettingGroup::Security => "Security Settings",
             SettingGroup::Backup => "Backup Settings",
             SettingGroup::Localization => "Localization Settings",
+            SettingGroup::Branding => "Branding Settings",
             SettingGroup::System => "System Settings",
         }
     }
@@ -135,6 +139,7 @@ impl SettingGroup {
             SettingGroup::Security,
             SettingGroup::Backup,
             SettingGroup::Localization,
+            SettingGroup::Branding,
             SettingGroup::System,
         ]
     }
@@ -319,8 +324,9 @@ mod tests {
     #[test]
     fn test_setting_group_all() {
         let groups = SettingGroup::all();
-        assert_eq!(groups.len(), 7);
+        assert_eq!(groups.len(), 8);
         assert!(groups.contains(&SettingGroup::Clinic));
         assert!(groups.contains(&SettingGroup::Security));
+        assert!(groups.contains(&SettingGroup::Branding));
     }
 }
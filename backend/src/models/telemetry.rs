@@ -0,0 +1,148 @@
+/*!
+ * Telemetry Model
+ *
+ * Anonymous operational metrics sent (opt-in) to a configurable endpoint.
+ * The payload only carries aggregate counts and installation metadata; it
+ * never includes patient data, user identifiers or free text.
+ */
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+/// Version of the payload format, bumped whenever fields change
+pub const TELEMETRY_SCHEMA_VERSION: u32 = 1;
+
+/// Anonymous telemetry payload
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TelemetryPayload {
+    pub schema_version: u32,
+    /// Application version
+    pub app_version: String,
+    /// Compile-time features enabled in this build
+    pub features: Vec<String>,
+    /// Deployment environment (development/production)
+    pub environment: String,
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    pub usage: TelemetryUsage,
+    pub errors: TelemetryErrors,
+}
+
+/// Feature usage counts over the reporting period
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, FromRow)]
+pub struct TelemetryUsage {
+    pub appointments_created: i64,
+    pub visits_created: i64,
+    pub prescriptions_created: i64,
+    pub documents_generated: i64,
+    pub notifications_queued: i64,
+    /// Users who logged in during the period
+    pub active_users: i64,
+}
+
+/// Failure counts for a background pipeline
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct TelemetryErrorRate {
+    pub total: i64,
+    pub failed: i64,
+    /// failed / total, 0 when nothing was processed
+    pub failure_rate: f64,
+}
+
+impl TelemetryErrorRate {
+    pub fn new(total: i64, failed: i64) -> Self {
+        let failure_rate = if total > 0 {
+            failed as f64 / total as f64
+        } else {
+            0.0
+        };
+        Self {
+            total,
+            failed,
+            failure_rate,
+        }
+    }
+}
+
+/// Error rates over the reporting period
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct TelemetryErrors {
+    pub notifications: TelemetryErrorRate,
+    pub documents: TelemetryErrorRate,
+    /// Internal alerts raised by background jobs, per alert type
+    pub system_alerts: BTreeMap<String, i64>,
+}
+
+/// Stored record of a report that was sent
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct TelemetryReport {
+    pub id: Uuid,
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    pub endpoint: String,
+    pub payload: serde_json::Value,
+    pub status_code: Option<i32>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Response for the telemetry preview endpoint
+#[derive(Debug, Clone, Serialize)]
+pub struct TelemetryPreviewResponse {
+    /// Whether the administrator has opted in
+    pub enabled: bool,
+    /// Configured endpoint, if any
+    pub endpoint: Option<String>,
+    pub interval_hours: i64,
+    /// When the last report was sent successfully
+    pub last_sent_at: Option<DateTime<Utc>>,
+    /// Exact body the next report would post
+    pub payload: TelemetryPayload,
+}
+
+/// Query parameters for listing sent reports
+#[derive(Debug, Clone, Deserialize)]
+pub struct ListTelemetryReportsQuery {
+    /// Maximum number of reports to return (default 20, max 100)
+    pub limit: Option<i64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_rate() {
+        let rate = TelemetryErrorRate::new(200, 5);
+        assert_eq!(rate.failure_rate, 0.025);
+        assert_eq!(TelemetryErrorRate::new(0, 0).failure_rate, 0.0);
+    }
+
+    #[test]
+    fn test_payload_has_no_identifiers() {
+        let payload = TelemetryPayload {
+            schema_version: TELEMETRY_SCHEMA_VERSION,
+            app_version: "1.0.0".to_string(),
+            features: vec!["rbac".to_string()],
+            environment: "production".to_string(),
+            period_start: Utc::now(),
+            period_end: Utc::now(),
+            usage: TelemetryUsage::default(),
+            errors: TelemetryErrors::default(),
+        };
+
+        let json = serde_json::to_value(&payload).unwrap();
+        let mut keys: Vec<&str> = json.as_object().unwrap().keys().map(|k| k.as_str()).collect();
+        keys.sort_unstable();
+        assert_eq!(
+            keys,
+            vec![
+                "app_version", "environment", "errors", "features",
+                "period_end", "period_start", "schema_version", "usage"
+            ]
+        );
+    }
+}
@@ -0,0 +1,166 @@
+/*!
+ * Televisit Model
+ *
+ * A TELEVISIT appointment takes place in a video room. The room is created
+ * by the configured `VideoRoomProvider` when the appointment is created (or
+ * later, on request) and kept in a session with the join link and the
+ * attendance of doctor and patient: the first time each joined and the last
+ * time each left.
+ *
+ * The patient gets an email with a link holding a signed token naming the
+ * appointment and the patient; the tokens are made and checked by
+ * `NotificationService`. Opening it records that the patient joined and
+ * hands out the room link. Rooms open `TELEVISIT_JOIN_EARLY_MINUTES` before
+ * the appointment and close `TELEVISIT_JOIN_GRACE_MINUTES` after its end.
+ */
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+use super::AppointmentStatus;
+
+/// Minutes before the start of a televisit that its room can be joined
+pub const TELEVISIT_JOIN_EARLY_MINUTES: i64 = 30;
+
+/// Minutes after the end of a televisit that its room can still be joined
+pub const TELEVISIT_JOIN_GRACE_MINUTES: i64 = 60;
+
+/// Time window in which a televisit room can be joined
+pub fn televisit_join_window(
+    scheduled_start: DateTime<Utc>,
+    scheduled_end: DateTime<Utc>,
+) -> (DateTime<Utc>, DateTime<Utc>) {
+    (
+        scheduled_start - Duration::minutes(TELEVISIT_JOIN_EARLY_MINUTES),
+        scheduled_end + Duration::minutes(TELEVISIT_JOIN_GRACE_MINUTES),
+    )
+}
+
+/// Whether a televisit can be joined at `now`
+///
+/// Cancelled, missed and completed appointments cannot be joined.
+pub fn televisit_joinable(
+    status: AppointmentStatus,
+    scheduled_start: DateTime<Utc>,
+    scheduled_end: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> bool {
+    let (opens_at, closes_at) = televisit_join_window(scheduled_start, scheduled_end);
+    !status.is_final() && now >= opens_at && now <= closes_at
+}
+
+/// Who joins or leaves a televisit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TelevisitParticipant {
+    Provider,
+    Patient,
+}
+
+/// Joining or leaving a televisit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TelevisitAttendance {
+    Join,
+    Leave,
+}
+
+impl TelevisitAttendance {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Join => "join",
+            Self::Leave => "leave",
+        }
+    }
+}
+
+/// Video room of a televisit, as stored
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct TelevisitSession {
+    pub id: Uuid,
+    pub appointment_id: Uuid,
+    pub patient_id: Uuid,
+    pub video_provider: String,
+    pub room_name: String,
+    pub join_url: String,
+    pub invitation_notification_id: Option<Uuid>,
+    pub provider_joined_at: Option<DateTime<Utc>>,
+    pub provider_left_at: Option<DateTime<Utc>>,
+    pub patient_joined_at: Option<DateTime<Utc>>,
+    pub patient_left_at: Option<DateTime<Utc>>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Contents of a valid televisit token
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TelevisitClaims {
+    pub appointment_id: Uuid,
+    pub patient_id: Uuid,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Request body for POST /api/v1/televisit/join and /leave
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct TelevisitTokenRequest {
+    #[validate(length(min = 1, max = 256, message = "Invalid link"))]
+    pub token: String,
+}
+
+/// Room link handed to the patient
+#[derive(Debug, Clone, Serialize)]
+pub struct TelevisitJoinResponse {
+    pub join_url: String,
+    pub scheduled_start: DateTime<Utc>,
+    pub scheduled_end: DateTime<Utc>,
+}
+
+/// Patient attendance after leaving
+#[derive(Debug, Clone, Serialize)]
+pub struct TelevisitLeaveResponse {
+    pub left_at: DateTime<Utc>,
+    pub message: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_join_window() {
+        let start = Utc::now();
+        let end = start + Duration::minutes(30);
+
+        let (opens_at, closes_at) = televisit_join_window(start, end);
+        assert_eq!(opens_at, start - Duration::minutes(30));
+        assert_eq!(closes_at, end + Duration::minutes(60));
+    }
+
+    #[test]
+    fn test_televisit_joinable() {
+        let start = Utc::now() + Duration::hours(1);
+        let end = start + Duration::minutes(30);
+        let joinable_at = |now| televisit_joinable(AppointmentStatus::Confirmed, start, end, now);
+
+        assert!(!joinable_at(start - Duration::minutes(31)));
+        assert!(joinable_at(start - Duration::minutes(30)));
+        assert!(joinable_at(end + Duration::minutes(60)));
+        assert!(!joinable_at(end + Duration::minutes(61)));
+
+        let joinable_when = |status| televisit_joinable(status, start, end, start);
+        assert!(!joinable_when(AppointmentStatus::Cancelled));
+        assert!(!joinable_when(AppointmentStatus::NoShow));
+        assert!(!joinable_when(AppointmentStatus::Completed));
+    }
+
+    #[test]
+    fn test_token_request_validation() {
+        let request: TelevisitTokenRequest = serde_json::from_str(r#"{"token": "abc"}"#).unwrap();
+        assert!(request.validate().is_ok());
+
+        let empty: TelevisitTokenRequest = serde_json::from_str(r#"{"token": ""}"#).unwrap();
+        assert!(empty.validate().is_err());
+    }
+}
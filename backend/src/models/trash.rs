@@ -0,0 +1,140 @@
+/*!
+ * Recycle Bin Model
+ *
+ * Deleted patients, draft visits and prescriptions stay in the recycle bin
+ * (their `deleted_at` is set) until restored, or purged by the RECYCLE_BIN
+ * retention rule.
+ */
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Kind of record in the recycle bin
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TrashEntityType {
+    Patient,
+    Visit,
+    Prescription,
+}
+
+impl TrashEntityType {
+    pub const ALL: [TrashEntityType; 3] = [
+        TrashEntityType::Patient,
+        TrashEntityType::Visit,
+        TrashEntityType::Prescription,
+    ];
+
+    /// Convert to database string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TrashEntityType::Patient => "PATIENT",
+            TrashEntityType::Visit => "VISIT",
+            TrashEntityType::Prescription => "PRESCRIPTION",
+        }
+    }
+
+    /// Parse from database string
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "PATIENT" => Some(TrashEntityType::Patient),
+            "VISIT" => Some(TrashEntityType::Visit),
+            "PRESCRIPTION" => Some(TrashEntityType::Prescription),
+            _ => None,
+        }
+    }
+
+    /// RBAC resource, and path segment of the restore endpoint
+    /// (`/trash/visits/:id/restore`)
+    pub fn resource(&self) -> &'static str {
+        match self {
+            TrashEntityType::Patient => "patients",
+            TrashEntityType::Visit => "visits",
+            TrashEntityType::Prescription => "prescriptions",
+        }
+    }
+
+    /// Parse the path segment of the restore endpoint
+    pub fn from_resource(resource: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|t| t.resource() == resource)
+    }
+}
+
+/// Query parameters for GET /api/v1/trash
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ListTrashQuery {
+    pub entity_type: Option<TrashEntityType>,
+    /// Default 50, max 200
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+impl ListTrashQuery {
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(50).clamp(1, 200)
+    }
+
+    pub fn offset(&self) -> i64 {
+        self.offset.unwrap_or(0).max(0)
+    }
+}
+
+/// Record in the recycle bin (API output)
+#[derive(Debug, Clone, Serialize)]
+pub struct TrashItem {
+    pub entity_type: TrashEntityType,
+    pub id: Uuid,
+    /// The patient itself for a deleted patient
+    pub patient_id: Uuid,
+    pub patient_name: Option<String>,
+    /// Visit type and date, or medication name
+    pub summary: Option<String>,
+    pub deleted_at: DateTime<Utc>,
+    pub deleted_by: Option<Uuid>,
+    pub deleted_by_name: Option<String>,
+    /// When the RECYCLE_BIN rule will purge it; none while the rule is
+    /// disabled or in dry-run mode
+    pub purge_after: Option<DateTime<Utc>>,
+}
+
+/// Recycle bin listing (API output)
+#[derive(Debug, Clone, Serialize)]
+pub struct TrashListResponse {
+    pub items: Vec<TrashItem>,
+    pub total: i64,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trash_entity_type_conversion() {
+        for entity_type in TrashEntityType::ALL {
+            assert_eq!(
+                TrashEntityType::from_str(entity_type.as_str()),
+                Some(entity_type)
+            );
+            assert_eq!(
+                TrashEntityType::from_resource(entity_type.resource()),
+                Some(entity_type)
+            );
+        }
+        assert_eq!(TrashEntityType::from_resource("appointments"), None);
+    }
+
+    #[test]
+    fn test_list_trash_query_bounds() {
+        let query = ListTrashQuery {
+            entity_type: None,
+            limit: Some(1000),
+            offset: Some(-5),
+        };
+        assert_eq!(query.limit(), 200);
+        assert_eq!(query.offset(), 0);
+        assert_eq!(ListTrashQuery::default().limit(), 50);
+    }
+}
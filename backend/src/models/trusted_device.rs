@@ -0,0 +1,125 @@
+/*!
+ * Trusted Device Model
+ *
+ * Devices on which a user chose "remember this device" after completing MFA.
+ * For 30 days, logins from that device skip the MFA code step.
+ *
+ * The device is identified by a signed cookie (a `device` JWT carrying the
+ * user ID and `trusted_devices.id`). The row is checked on every use, so
+ * revoking it invalidates the cookie immediately.
+ */
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// How long a device stays trusted
+pub const TRUSTED_DEVICE_DAYS: i64 = 30;
+
+/// Name of the trusted device cookie
+pub const TRUSTED_DEVICE_COOKIE: &str = "docpat_trusted_device";
+
+/// Maximum stored length of the device label (from the User-Agent)
+const MAX_DEVICE_NAME_LEN: usize = 255;
+
+/// Trusted device row
+#[derive(Debug, Clone, FromRow)]
+pub struct TrustedDevice {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub device_name: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl TrustedDevice {
+    /// Expiry for a device trusted now
+    pub fn expiry_from(now: DateTime<Utc>) -> DateTime<Utc> {
+        now + Duration::days(TRUSTED_DEVICE_DAYS)
+    }
+
+    /// Whether the device can still be used to skip MFA
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.revoked_at.is_none() && self.expires_at > now
+    }
+
+    /// Device label derived from the User-Agent
+    pub fn device_name_from_user_agent(user_agent: Option<&str>) -> Option<String> {
+        user_agent
+            .map(str::trim)
+            .filter(|ua| !ua.is_empty())
+            .map(|ua| ua.chars().take(MAX_DEVICE_NAME_LEN).collect())
+    }
+}
+
+/// Trusted device as returned by the API
+#[derive(Debug, Clone, Serialize)]
+pub struct TrustedDeviceResponse {
+    pub id: Uuid,
+    pub device_name: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub expires_at: DateTime<Utc>,
+    /// Whether this is the device making the request
+    pub current: bool,
+}
+
+impl TrustedDeviceResponse {
+    pub fn from_device(device: TrustedDevice, current_device_id: Option<Uuid>) -> Self {
+        Self {
+            current: current_device_id == Some(device.id),
+            id: device.id,
+            device_name: device.device_name,
+            ip_address: device.ip_address,
+            created_at: device.created_at,
+            last_used_at: device.last_used_at,
+            expires_at: device.expires_at,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(expires_at: DateTime<Utc>, revoked_at: Option<DateTime<Utc>>) -> TrustedDevice {
+        TrustedDevice {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            device_name: None,
+            ip_address: None,
+            created_at: Utc::now(),
+            last_used_at: None,
+            expires_at,
+            revoked_at,
+        }
+    }
+
+    #[test]
+    fn test_is_active() {
+        let now = Utc::now();
+        assert!(device(TrustedDevice::expiry_from(now), None).is_active(now));
+        assert!(!device(now - Duration::seconds(1), None).is_active(now));
+        assert!(!device(TrustedDevice::expiry_from(now), Some(now)).is_active(now));
+    }
+
+    #[test]
+    fn test_device_name_from_user_agent() {
+        assert_eq!(TrustedDevice::device_name_from_user_agent(None), None);
+        assert_eq!(TrustedDevice::device_name_from_user_agent(Some("  ")), None);
+        assert_eq!(
+            TrustedDevice::device_name_from_user_agent(Some("Firefox")),
+            Some("Firefox".to_string())
+        );
+        let long = "x".repeat(400);
+        assert_eq!(
+            TrustedDevice::device_name_from_user_agent(Some(&long)).unwrap().len(),
+            MAX_DEVICE_NAME_LEN
+        );
+    }
+}
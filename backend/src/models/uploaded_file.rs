@@ -26,6 +26,14 @@ pub enum FilePurpose {
     Document,
     /// User avatar (future)
     Avatar,
+    /// White-label theming asset (logo variants, email header)
+    Branding,
+    /// Patient identification photo and its thumbnail (encrypted at rest)
+    PatientPhoto,
+    /// External document on the patient record (encrypted at rest)
+    PatientAttachment,
+    /// Clinical photo, audio memo or file attached to a visit (encrypted at rest)
+    VisitAttachment,
 }
 
 impl fmt::Display for FilePurpose {
@@ -35,6 +43,10 @@ impl fmt::Display for FilePurpose {
             FilePurpose::Attachment => write!(f, "ATTACHMENT"),
             FilePurpose::Document => write!(f, "DOCUMENT"),
             FilePurpose::Avatar => write!(f, "AVATAR"),
+            FilePurpose::Branding => write!(f, "BRANDING"),
+            FilePurpose::PatientPhoto => write!(f, "PATIENT_PHOTO"),
+            FilePurpose::PatientAttachment => write!(f, "PATIENT_ATTACHMENT"),
+            FilePurpose::VisitAttachment => write!(f, "VISIT_ATTACHMENT"),
         }
     }
 }
@@ -47,6 +59,10 @@ impl FilePurpose {
             "ATTACHMENT" => Some(FilePurpose::Attachment),
             "DOCUMENT" => Some(FilePurpose::Document),
             "AVATAR" => Some(FilePurpose::Avatar),
+            "BRANDING" => Some(FilePurpose::Branding),
+            "PATIENT_PHOTO" => Some(FilePurpose::PatientPhoto),
+            "PATIENT_ATTACHMENT" => Some(FilePurpose::PatientAttachment),
+            "VISIT_ATTACHMENT" => Some(FilePurpose::VisitAttachment),
             _ => None,
         }
     }
@@ -58,8 +74,23 @@ impl FilePurpose {
             FilePurpose::Attachment => "attachments",
             FilePurpose::Document => "documents",
             FilePurpose::Avatar => "avatars",
+            FilePurpose::Branding => "branding",
+            FilePurpose::PatientPhoto => "patient-photos",
+            FilePurpose::PatientAttachment => "patient-attachments",
+            FilePurpose::VisitAttachment => "visit-attachments",
         }
     }
+
+    /// Whether files of this purpose belong to a patient record
+    ///
+    /// Such files are stored encrypted and only served through the patient
+    /// endpoints, never through the generic file endpoints.
+    pub fn is_patient_data(&self) -> bool {
+        matches!(
+            self,
+            FilePurpose::PatientPhoto | FilePurpose::PatientAttachment | FilePurpose::VisitAttachment
+        )
+    }
 }
 
 // ==================== Database Model ====================
@@ -82,6 +113,8 @@ pub struct UploadedFile {
     pub updated_by: Option<Uuid>,
     pub updated_at: DateTime<Utc>,
     pub deleted_at: Option<DateTime<Utc>>,
+    /// Content on disk is AES-256-GCM encrypted (size and hash are of the plaintext)
+    pub is_encrypted: bool,
 }
 
 // ==================== Response DTOs ====================
@@ -196,6 +229,33 @@ pub const ALLOWED_DOCUMENT_MIME_TYPES: &[&str] = &[
     "image/png",
 ];
 
+/// Allowed MIME types for patient photos (no SVG: photos are decoded for thumbnails)
+pub const ALLOWED_PATIENT_PHOTO_MIME_TYPES: &[&str] = &[
+    "image/jpeg",
+    "image/png",
+    "image/webp",
+];
+
+/// Allowed MIME types for visit attachments: clinical photos, audio memos
+/// (as recorded by browsers and phones) and PDF documents
+pub const ALLOWED_VISIT_ATTACHMENT_MIME_TYPES: &[&str] = &[
+    "image/jpeg",
+    "image/png",
+    "image/webp",
+    "audio/mpeg",
+    "audio/wav",
+    "audio/ogg",
+    "audio/mp4",
+    "audio/webm",
+    "application/pdf",
+];
+
+/// Maximum patient photo size in bytes (5 MB)
+pub const MAX_PATIENT_PHOTO_SIZE: usize = 5 * 1024 * 1024;
+
+/// Longest side of a patient photo thumbnail, in pixels
+pub const PATIENT_PHOTO_THUMBNAIL_SIZE: u32 = 256;
+
 /// Magic bytes signatures for common file types
 pub mod magic_bytes {
     /// JPEG signature (0xFF 0xD8 0xFF)
@@ -211,6 +271,16 @@ pub mod magic_bytes {
     pub const WEBP_MARKER: &[u8] = b"WEBP";
     /// PDF signature
     pub const PDF: &[u8] = b"%PDF";
+    /// MP3 with an ID3 tag
+    pub const MP3_ID3: &[u8] = b"ID3";
+    /// WAV signature (RIFF....WAVE)
+    pub const WAV_MARKER: &[u8] = b"WAVE";
+    /// Ogg container (Opus/Vorbis audio)
+    pub const OGG: &[u8] = b"OggS";
+    /// ISO base media box type at offset 4 (M4A/MP4 audio)
+    pub const MP4_FTYP: &[u8] = b"ftyp";
+    /// EBML header (WebM)
+    pub const WEBM: &[u8] = &[0x1A, 0x45, 0xDF, 0xA3];
     /// SVG detection (starts with XML or <svg)
     pub const SVG_XML: &[u8] = b"<?xml";
     pub const SVG_TAG: &[u8] = b"<svg";
@@ -284,6 +354,8 @@ mod tests {
         assert_eq!(FilePurpose::Attachment.to_string(), "ATTACHMENT");
         assert_eq!(FilePurpose::Document.to_string(), "DOCUMENT");
         assert_eq!(FilePurpose::Avatar.to_string(), "AVATAR");
+        assert_eq!(FilePurpose::Branding.to_string(), "BRANDING");
+        assert_eq!(FilePurpose::PatientPhoto.to_string(), "PATIENT_PHOTO");
     }
 
     #[test]
@@ -292,6 +364,7 @@ mod tests {
         assert_eq!(FilePurpose::from_str("logo"), Some(FilePurpose::Logo));
         assert_eq!(FilePurpose::from_str("Logo"), Some(FilePurpose::Logo));
         assert_eq!(FilePurpose::from_str("attachment"), Some(FilePurpose::Attachment));
+        assert_eq!(FilePurpose::from_str("patient_photo"), Some(FilePurpose::PatientPhoto));
         assert_eq!(FilePurpose::from_str("INVALID"), None);
     }
 
@@ -301,6 +374,19 @@ mod tests {
         assert_eq!(FilePurpose::Attachment.subdirectory(), "attachments");
         assert_eq!(FilePurpose::Document.subdirectory(), "documents");
         assert_eq!(FilePurpose::Avatar.subdirectory(), "avatars");
+        assert_eq!(FilePurpose::Branding.subdirectory(), "branding");
+        assert_eq!(FilePurpose::PatientPhoto.subdirectory(), "patient-photos");
+        assert_eq!(FilePurpose::PatientAttachment.subdirectory(), "patient-attachments");
+        assert_eq!(FilePurpose::VisitAttachment.subdirectory(), "visit-attachments");
+    }
+
+    #[test]
+    fn test_file_purpose_is_patient_data() {
+        assert!(FilePurpose::PatientPhoto.is_patient_data());
+        assert!(FilePurpose::PatientAttachment.is_patient_data());
+        assert!(FilePurpose::VisitAttachment.is_patient_data());
+        assert!(!FilePurpose::Attachment.is_patient_data());
+        assert!(!FilePurpose::Logo.is_patient_data());
     }
 
     #[test]
@@ -23,6 +23,12 @@ pub enum UserRole {
     /// Doctor with patient care access
     #[sqlx(rename = "DOCTOR")]
     Doctor,
+    /// Nurse: vitals and draft visits; signs only under a co-signature requirement
+    #[sqlx(rename = "NURSE")]
+    Nurse,
+    /// Front desk: scheduling and patient demographics, no clinical data
+    #[sqlx(rename = "RECEPTIONIST")]
+    Receptionist,
 }
 
 impl std::fmt::Display for UserRole {
@@ -30,6 +36,8 @@ impl std::fmt::Display for UserRole {
         match self {
             UserRole::Admin => write!(f, "ADMIN"),
             UserRole::Doctor => write!(f, "DOCTOR"),
+            UserRole::Nurse => write!(f, "NURSE"),
+            UserRole::Receptionist => write!(f, "RECEPTIONIST"),
         }
     }
 }
@@ -46,7 +54,7 @@ pub struct User {
     /// Argon2 hashed password
     #[serde(skip_serializing)]
     pub password_hash: String,
-    /// User role (ADMIN or DOCTOR)
+    /// User role (ADMIN, DOCTOR, NURSE or RECEPTIONIST)
     pub role: UserRole,
     /// First name
     pub first_name: String,
@@ -209,12 +217,16 @@ impl User {
     /// * `pool` - Database connection pool
     /// * `max_attempts` - Maximum allowed failed attempts before lockout
     /// * `lockout_duration_secs` - Duration of lockout in seconds
+    ///
+    /// # Returns
+    ///
+    /// The lockout expiry when this attempt locked the account
     pub async fn increment_failed_login(
         &self,
         pool: &PgPool,
         max_attempts: u32,
         lockout_duration_secs: i64,
-    ) -> Result<()> {
+    ) -> Result<Option<DateTime<Utc>>> {
         let new_attempts = self.failed_login_attempts + 1;
         let locked_until = if new_attempts >= max_attempts as i32 {
             Some(Utc::now() + chrono::Duration::seconds(lockout_duration_secs))
@@ -237,7 +249,7 @@ impl User {
         .execute(pool)
         .await?;
 
-        Ok(())
+        Ok(locked_until)
     }
 
     /// Set MFA secret for the user
@@ -337,6 +349,8 @@ mod tests {
     fn test_user_role_display() {
         assert_eq!(UserRole::Admin.to_string(), "ADMIN");
         assert_eq!(UserRole::Doctor.to_string(), "DOCTOR");
+        assert_eq!(UserRole::Nurse.to_string(), "NURSE");
+        assert_eq!(UserRole::Receptionist.to_string(), "RECEPTIONIST");
     }
 
     #[test]
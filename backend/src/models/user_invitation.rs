@@ -0,0 +1,355 @@
+/*!
+ * User Invitation Model
+ *
+ * Data models for the staff invitation flow:
+ * - An admin invites a new user by email with a pre-assigned role
+ * - The invitee receives a tokenized link, sets a username/password and
+ *   enrolls MFA; the user account is created only at that point
+ *
+ * Invitation tokens are random 256-bit values sent by email. Only their
+ * SHA-256 hash is stored in `user_invitations.token_hash`.
+ */
+
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::models::user::UserRole;
+
+/// Default invitation validity when the setting is missing
+pub const DEFAULT_INVITATION_EXPIRY_HOURS: i64 = 72;
+
+/// Setting key for the invitation validity window
+pub const INVITATION_EXPIRY_SETTING_KEY: &str = "security.invitation_expiry_hours";
+
+// ============================================================================
+// Enums
+// ============================================================================
+
+/// Derived invitation status
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum InvitationStatus {
+    /// Sent and still usable
+    Pending,
+    /// Invitee created their account
+    Accepted,
+    /// Withdrawn by an admin (or superseded by a re-send)
+    Revoked,
+    /// Validity window elapsed without acceptance
+    Expired,
+}
+
+impl InvitationStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InvitationStatus::Pending => "PENDING",
+            InvitationStatus::Accepted => "ACCEPTED",
+            InvitationStatus::Revoked => "REVOKED",
+            InvitationStatus::Expired => "EXPIRED",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_uppercase().as_str() {
+            "PENDING" => Some(InvitationStatus::Pending),
+            "ACCEPTED" => Some(InvitationStatus::Accepted),
+            "REVOKED" => Some(InvitationStatus::Revoked),
+            "EXPIRED" => Some(InvitationStatus::Expired),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for InvitationStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+// ============================================================================
+// Database Model
+// ============================================================================
+
+/// User invitation row
+#[derive(Debug, Clone, FromRow)]
+pub struct UserInvitation {
+    pub id: Uuid,
+    pub email: String,
+    pub role: UserRole,
+    pub first_name: String,
+    pub last_name: String,
+    pub phone: Option<String>,
+    pub token_hash: String,
+    pub expires_at: DateTime<Utc>,
+    pub accepted_at: Option<DateTime<Utc>>,
+    pub accepted_user_id: Option<Uuid>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub invited_by: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl UserInvitation {
+    /// Current status, derived from the lifecycle timestamps
+    pub fn status(&self) -> InvitationStatus {
+        self.status_at(Utc::now())
+    }
+
+    /// Status as of a given instant
+    pub fn status_at(&self, now: DateTime<Utc>) -> InvitationStatus {
+        if self.accepted_at.is_some() {
+            InvitationStatus::Accepted
+        } else if self.revoked_at.is_some() {
+            InvitationStatus::Revoked
+        } else if self.expires_at <= now {
+            InvitationStatus::Expired
+        } else {
+            InvitationStatus::Pending
+        }
+    }
+}
+
+// ============================================================================
+// Tokens
+// ============================================================================
+
+/// Generate a new invitation token (64 hex chars)
+pub fn generate_invitation_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// Hash an invitation token for storage/lookup
+pub fn hash_invitation_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.trim().as_bytes()))
+}
+
+// ============================================================================
+// Request DTOs
+// ============================================================================
+
+/// Invite a new user
+///
+/// POST /api/v1/users/invitations
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct CreateInvitationRequest {
+    #[validate(email(message = "Invalid email address"))]
+    pub email: String,
+
+    pub role: UserRole,
+
+    #[validate(length(min = 1, max = 100, message = "First name must be 1-100 characters"))]
+    pub first_name: String,
+
+    #[validate(length(min = 1, max = 100, message = "Last name must be 1-100 characters"))]
+    pub last_name: String,
+
+    #[validate(length(max = 20, message = "Phone must be at most 20 characters"))]
+    pub phone: Option<String>,
+}
+
+/// Invitation token supplied by the invitee
+///
+/// The token travels in the body rather than the URL so that it does not
+/// end up in request audit logs or proxy access logs.
+#[derive(Debug, Clone, Deserialize)]
+pub struct InvitationTokenRequest {
+    pub token: String,
+}
+
+/// Accept an invitation: choose credentials and confirm MFA enrollment
+///
+/// POST /api/v1/auth/invitations/accept
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct AcceptInvitationRequest {
+    pub token: String,
+
+    #[validate(length(min = 3, max = 50, message = "Username must be 3-50 characters"))]
+    pub username: String,
+
+    pub password: String,
+
+    /// TOTP secret returned by the MFA setup step
+    pub mfa_secret: String,
+
+    /// Current code from the authenticator app
+    pub mfa_code: String,
+
+    /// Backup codes returned by the MFA setup step (stored hashed)
+    pub backup_codes: Vec<String>,
+}
+
+/// Query parameters for listing invitations
+#[derive(Debug, Clone, Deserialize)]
+pub struct ListInvitationsQuery {
+    /// Filter by derived status (PENDING, ACCEPTED, REVOKED, EXPIRED)
+    pub status: Option<String>,
+}
+
+// ============================================================================
+// Response DTOs
+// ============================================================================
+
+/// Invitation as seen by admins
+#[derive(Debug, Clone, Serialize)]
+pub struct InvitationResponse {
+    pub id: Uuid,
+    pub email: String,
+    pub role: UserRole,
+    pub first_name: String,
+    pub last_name: String,
+    pub phone: Option<String>,
+    pub status: InvitationStatus,
+    pub expires_at: DateTime<Utc>,
+    pub accepted_at: Option<DateTime<Utc>>,
+    pub accepted_user_id: Option<Uuid>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub invited_by: Uuid,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<UserInvitation> for InvitationResponse {
+    fn from(invitation: UserInvitation) -> Self {
+        Self {
+            status: invitation.status(),
+            id: invitation.id,
+            email: invitation.email,
+            role: invitation.role,
+            first_name: invitation.first_name,
+            last_name: invitation.last_name,
+            phone: invitation.phone,
+            expires_at: invitation.expires_at,
+            accepted_at: invitation.accepted_at,
+            accepted_user_id: invitation.accepted_user_id,
+            revoked_at: invitation.revoked_at,
+            invited_by: invitation.invited_by,
+            created_at: invitation.created_at,
+        }
+    }
+}
+
+/// Result of sending (or re-sending) an invitation
+#[derive(Debug, Clone, Serialize)]
+pub struct SendInvitationResponse {
+    pub invitation: InvitationResponse,
+    /// Whether the invitation email was delivered to the mail server
+    pub email_sent: bool,
+    /// Invitation link, only returned when the email could not be sent so the
+    /// admin can forward it through another secure channel
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub invitation_url: Option<String>,
+}
+
+/// Invitation details shown to the invitee before accepting
+#[derive(Debug, Clone, Serialize)]
+pub struct PublicInvitationResponse {
+    pub email: String,
+    pub role: UserRole,
+    pub first_name: String,
+    pub last_name: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl From<&UserInvitation> for PublicInvitationResponse {
+    fn from(invitation: &UserInvitation) -> Self {
+        Self {
+            email: invitation.email.clone(),
+            role: invitation.role.clone(),
+            first_name: invitation.first_name.clone(),
+            last_name: invitation.last_name.clone(),
+            expires_at: invitation.expires_at,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn invitation() -> UserInvitation {
+        let now = Utc::now();
+        UserInvitation {
+            id: Uuid::new_v4(),
+            email: "new.doctor@example.com".to_string(),
+            role: UserRole::Doctor,
+            first_name: "Anna".to_string(),
+            last_name: "Rossi".to_string(),
+            phone: None,
+            token_hash: hash_invitation_token("token"),
+            expires_at: now + Duration::hours(72),
+            accepted_at: None,
+            accepted_user_id: None,
+            revoked_at: None,
+            invited_by: Uuid::new_v4(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    #[test]
+    fn test_generate_invitation_token() {
+        let a = generate_invitation_token();
+        let b = generate_invitation_token();
+        assert_eq!(a.len(), 64);
+        assert!(a.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn test_hash_invitation_token() {
+        let token = generate_invitation_token();
+        let hash = hash_invitation_token(&token);
+        assert_eq!(hash.len(), 64);
+        assert_ne!(hash, token);
+        // Whitespace from copy/paste does not change the lookup
+        assert_eq!(hash_invitation_token(&format!(" {}\n", token)), hash);
+    }
+
+    #[test]
+    fn test_invitation_status() {
+        let mut inv = invitation();
+        assert_eq!(inv.status(), InvitationStatus::Pending);
+
+        assert_eq!(
+            inv.status_at(inv.expires_at + Duration::seconds(1)),
+            InvitationStatus::Expired
+        );
+
+        inv.revoked_at = Some(Utc::now());
+        assert_eq!(inv.status(), InvitationStatus::Revoked);
+
+        inv.revoked_at = None;
+        inv.accepted_at = Some(Utc::now());
+        assert_eq!(inv.status(), InvitationStatus::Accepted);
+    }
+
+    #[test]
+    fn test_invitation_status_roundtrip() {
+        for status in [
+            InvitationStatus::Pending,
+            InvitationStatus::Accepted,
+            InvitationStatus::Revoked,
+            InvitationStatus::Expired,
+        ] {
+            assert_eq!(InvitationStatus::from_str(status.as_str()), Some(status));
+        }
+        assert_eq!(InvitationStatus::from_str("pending"), Some(InvitationStatus::Pending));
+        assert_eq!(InvitationStatus::from_str("OPEN"), None);
+    }
+
+    #[test]
+    fn test_public_response_hides_internal_fields() {
+        let inv = invitation();
+        let json = serde_json::to_value(PublicInvitationResponse::from(&inv)).unwrap();
+        assert!(json.get("token_hash").is_none());
+        assert!(json.get("invited_by").is_none());
+        assert_eq!(json["role"], "DOCTOR");
+    }
+}
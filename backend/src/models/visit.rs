@@ -7,12 +7,15 @@
  * Status Workflow:
  * - DRAFT → SIGNED → LOCKED
  * - DRAFT can be edited freely
+ * - DRAFT → PENDING_COSIGN when the signer needs a co-signature; the supervisor
+ *   countersigns it (→ SIGNED) or returns it (→ DRAFT)
  * - SIGNED visits cannot be edited (can only be locked)
  * - LOCKED visits are immutable and permanently archived
  *
  * The visits table is partitioned by year (visit_date) for performance and retention management.
  */
 
+use crate::models::{AllergySummary, VisitAttachmentResponse};
 use crate::utils::encryption::EncryptionKey;
 use anyhow::{Context, Result};
 use chrono::{DateTime, NaiveDate, Utc};
@@ -28,6 +31,9 @@ use validator::Validate;
 pub enum VisitStatus {
     /// Visit note is in draft state (can be edited)
     Draft,
+    /// Visit note has been signed by a user under supervision and awaits the
+    /// supervisor's co-signature (cannot be edited)
+    PendingCosign,
     /// Visit note has been digitally signed (cannot be edited, can be locked)
     Signed,
     /// Visit note is permanently locked (immutable)
@@ -38,7 +44,14 @@ impl VisitStatus {
     /// Check if transition from current status to new status is valid
     pub fn can_transition_to(&self, new_status: &VisitStatus) -> bool {
         match self {
-            VisitStatus::Draft => matches!(new_status, VisitStatus::Draft | VisitStatus::Signed),
+            VisitStatus::Draft => matches!(
+                new_status,
+                VisitStatus::Draft | VisitStatus::Signed | VisitStatus::PendingCosign
+            ),
+            VisitStatus::PendingCosign => matches!(
+                new_status,
+                VisitStatus::PendingCosign | VisitStatus::Signed | VisitStatus::Draft
+            ),
             VisitStatus::Signed => matches!(new_status, VisitStatus::Signed | VisitStatus::Locked),
             VisitStatus::Locked => *self == *new_status, // LOCKED is final
         }
@@ -203,6 +216,12 @@ pub struct Visit {
     pub signed_by: Option<Uuid>,
     pub signature_hash: Option<String>,
 
+    // Co-signature
+    pub cosign_supervisor_id: Option<Uuid>,
+    pub cosigned_by: Option<Uuid>,
+    pub cosigned_at: Option<DateTime<Utc>>,
+    pub cosign_return_note: Option<String>, // 🔒 Encrypted
+
     // Lock timestamp
     pub locked_at: Option<DateTime<Utc>>,
 
@@ -332,6 +351,10 @@ pub struct UpdateVisitRequest {
 
     // Attachments
     pub attachment_urls: Option<Vec<String>>,
+
+    /// `updated_at` of the visit as read; the update is refused with 409 if
+    /// it changed since (same as `If-Match`)
+    pub expected_version: Option<DateTime<Utc>>,
 }
 
 /// Visit response (API output with decrypted data)
@@ -376,6 +399,10 @@ pub struct VisitResponse {
     pub signed_by: Option<Uuid>,
     pub signed_by_name: Option<String>,
     pub signature_hash: Option<String>,
+    pub cosign_supervisor_id: Option<Uuid>,
+    pub cosigned_by: Option<Uuid>,
+    pub cosigned_at: Option<DateTime<Utc>>,
+    pub cosign_return_note: Option<String>,
     pub locked_at: Option<DateTime<Utc>>,
 
     // Version
@@ -390,6 +417,13 @@ pub struct VisitResponse {
     // Attachments
     pub has_attachments: bool,
     pub attachment_urls: Option<Vec<String>>,
+    // Photos, audio memos and files (only when fetching a single visit)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attachments: Option<Vec<VisitAttachmentResponse>>,
+
+    // Patient's active allergies (only when fetching a single visit)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub patient_allergies: Option<Vec<AllergySummary>>,
 
     // Audit
     pub created_at: DateTime<Utc>,
@@ -500,6 +534,13 @@ impl Visit {
             .transpose()
             .context("Failed to decrypt follow_up_notes")?;
 
+        let cosign_return_note = self
+            .cosign_return_note
+            .as_ref()
+            .map(|enc| encryption_key.decrypt(enc))
+            .transpose()
+            .context("Failed to decrypt cosign_return_note")?;
+
         Ok(VisitResponse {
             id: self.id,
             appointment_id: self.appointment_id,
@@ -527,6 +568,10 @@ impl Visit {
             signed_by: self.signed_by,
             signed_by_name,
             signature_hash: self.signature_hash.clone(),
+            cosign_supervisor_id: self.cosign_supervisor_id,
+            cosigned_by: self.cosigned_by,
+            cosigned_at: self.cosigned_at,
+            cosign_return_note,
             locked_at: self.locked_at,
             version: self.version,
             last_autosave_at: self.last_autosave_at,
@@ -535,6 +580,8 @@ impl Visit {
             follow_up_notes,
             has_attachments: self.has_attachments,
             attachment_urls: self.attachment_urls.clone(),
+            attachments: None,
+            patient_allergies: None,
             created_at: self.created_at,
             updated_at: self.updated_at,
             created_by: self.created_by,
@@ -574,6 +621,13 @@ mod tests {
         assert!(VisitStatus::Signed.can_transition_to(&VisitStatus::Signed));
         assert!(!VisitStatus::Signed.can_transition_to(&VisitStatus::Draft));
 
+        // PENDING_COSIGN transitions
+        assert!(VisitStatus::Draft.can_transition_to(&VisitStatus::PendingCosign));
+        assert!(VisitStatus::PendingCosign.can_transition_to(&VisitStatus::Signed));
+        assert!(VisitStatus::PendingCosign.can_transition_to(&VisitStatus::Draft));
+        assert!(!VisitStatus::PendingCosign.can_transition_to(&VisitStatus::Locked));
+        assert!(!VisitStatus::Signed.can_transition_to(&VisitStatus::PendingCosign));
+
         // LOCKED is final
         assert!(VisitStatus::Locked.can_transition_to(&VisitStatus::Locked));
         assert!(!VisitStatus::Locked.can_transition_to(&VisitStatus::Draft));
@@ -583,6 +637,7 @@ mod tests {
     #[test]
     fn test_visit_status_editable() {
         assert!(VisitStatus::Draft.is_editable());
+        assert!(!VisitStatus::PendingCosign.is_editable());
         assert!(!VisitStatus::Signed.is_editable());
         assert!(!VisitStatus::Locked.is_editable());
     }
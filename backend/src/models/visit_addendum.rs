@@ -0,0 +1,262 @@
+/*!
+ * Visit Addendum Model
+ *
+ * Signed amendments appended to a signed or locked visit. The visit note is
+ * never changed; an addendum adds to it or corrects it, and is signed by its
+ * author when it is created. The reason and the content are encrypted.
+ */
+
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
+
+use crate::utils::encryption::EncryptionKey;
+
+/// Kind of addendum
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AddendumType {
+    /// Adds information to the note (late results, follow-up)
+    Addendum,
+    /// Amends something in the note; needs a reason
+    Correction,
+}
+
+impl AddendumType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AddendumType::Addendum => "ADDENDUM",
+            AddendumType::Correction => "CORRECTION",
+        }
+    }
+}
+
+/// Addendum row (encrypted)
+#[derive(Debug, Clone, FromRow)]
+pub struct VisitAddendum {
+    pub id: Uuid,
+    pub visit_id: Uuid,
+    pub visit_date: NaiveDate,
+    pub patient_id: Uuid,
+    pub addendum_type: String,
+    pub reason: Option<String>, // 🔒 Encrypted
+    pub content: String,        // 🔒 Encrypted
+    pub signed_by: Uuid,
+    pub signed_by_name: Option<String>,
+    pub signed_at: DateTime<Utc>,
+    pub signature_hash: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl VisitAddendum {
+    /// Decrypt into the API representation, checking the signature against
+    /// the visit's signature hash
+    pub fn decrypt(
+        &self,
+        key: &EncryptionKey,
+        visit_signature_hash: &str,
+    ) -> Result<VisitAddendumResponse> {
+        let reason = self.reason.as_deref().map(|r| key.decrypt(r)).transpose()?;
+        let content = key.decrypt(&self.content)?;
+
+        let expected = addendum_signature_hash(
+            visit_signature_hash,
+            &self.addendum_type,
+            reason.as_deref(),
+            &content,
+            self.signed_by,
+            self.signed_at,
+        );
+
+        Ok(VisitAddendumResponse {
+            id: self.id,
+            visit_id: self.visit_id,
+            visit_date: self.visit_date,
+            patient_id: self.patient_id,
+            addendum_type: self.addendum_type.clone(),
+            reason,
+            content,
+            signed_by: self.signed_by,
+            signed_by_name: self.signed_by_name.clone(),
+            signed_at: self.signed_at,
+            signature_hash: self.signature_hash.clone(),
+            signature_valid: expected == self.signature_hash,
+            created_at: self.created_at,
+        })
+    }
+}
+
+/// Addendum to a visit (API output)
+#[derive(Debug, Clone, Serialize)]
+pub struct VisitAddendumResponse {
+    pub id: Uuid,
+    pub visit_id: Uuid,
+    pub visit_date: NaiveDate,
+    pub patient_id: Uuid,
+    pub addendum_type: String,
+    pub reason: Option<String>,
+    pub content: String,
+    pub signed_by: Uuid,
+    pub signed_by_name: Option<String>,
+    pub signed_at: DateTime<Utc>,
+    pub signature_hash: String,
+    /// Whether the stored signature still matches the addendum and the visit
+    pub signature_valid: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Request body for POST /api/v1/visits/:id/addenda
+#[derive(Debug, Clone, Deserialize, Validate)]
+#[validate(schema(function = "validate_addendum_reason"))]
+pub struct CreateVisitAddendumRequest {
+    pub addendum_type: AddendumType,
+
+    #[validate(length(max = 1000, message = "Reason too long (max 1000 chars)"))]
+    pub reason: Option<String>,
+
+    #[validate(length(min = 1, max = 10000, message = "Content must be 1-10000 characters"))]
+    pub content: String,
+}
+
+/// A correction must say why
+fn validate_addendum_reason(
+    request: &CreateVisitAddendumRequest,
+) -> std::result::Result<(), ValidationError> {
+    let has_reason = request
+        .reason
+        .as_deref()
+        .is_some_and(|r| !r.trim().is_empty());
+
+    if request.addendum_type == AddendumType::Correction && !has_reason {
+        let mut error = ValidationError::new("reason_required");
+        error.message = Some("A correction requires a reason".into());
+        return Err(error);
+    }
+    Ok(())
+}
+
+/// SHA-256 signature of an addendum
+///
+/// Covers the visit's own signature hash, so an addendum stays tied to the
+/// note it amends, plus everything the author signed.
+pub fn addendum_signature_hash(
+    visit_signature_hash: &str,
+    addendum_type: &str,
+    reason: Option<&str>,
+    content: &str,
+    signed_by: Uuid,
+    signed_at: DateTime<Utc>,
+) -> String {
+    let mut hasher = Sha256::new();
+    for part in [
+        visit_signature_hash,
+        addendum_type,
+        reason.unwrap_or(""),
+        content,
+        signed_by.to_string().as_str(),
+        signed_at.to_rfc3339().as_str(),
+    ] {
+        hasher.update(part.as_bytes());
+        hasher.update([0u8]);
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_addendum_signature_hash() {
+        let signed_by = Uuid::new_v4();
+        let signed_at = Utc.with_ymd_and_hms(2026, 4, 12, 10, 30, 0).unwrap();
+        let hash = addendum_signature_hash(
+            "abc",
+            "ADDENDUM",
+            None,
+            "Esami nella norma",
+            signed_by,
+            signed_at,
+        );
+
+        assert_eq!(hash.len(), 64);
+        assert_eq!(
+            hash,
+            addendum_signature_hash(
+                "abc",
+                "ADDENDUM",
+                None,
+                "Esami nella norma",
+                signed_by,
+                signed_at
+            )
+        );
+        // Changing the visit, the content or the author breaks the signature
+        assert_ne!(
+            hash,
+            addendum_signature_hash(
+                "abd",
+                "ADDENDUM",
+                None,
+                "Esami nella norma",
+                signed_by,
+                signed_at
+            )
+        );
+        assert_ne!(
+            hash,
+            addendum_signature_hash(
+                "abc",
+                "ADDENDUM",
+                None,
+                "Esami alterati",
+                signed_by,
+                signed_at
+            )
+        );
+        assert_ne!(
+            hash,
+            addendum_signature_hash(
+                "abc",
+                "ADDENDUM",
+                None,
+                "Esami nella norma",
+                Uuid::new_v4(),
+                signed_at
+            )
+        );
+        // Fields are delimited, so moving text between them changes the hash
+        assert_ne!(
+            addendum_signature_hash("abc", "CORRECTION", Some("ab"), "c", signed_by, signed_at),
+            addendum_signature_hash("abc", "CORRECTION", Some("a"), "bc", signed_by, signed_at)
+        );
+    }
+
+    #[test]
+    fn test_correction_requires_reason() {
+        let request: CreateVisitAddendumRequest = serde_json::from_value(serde_json::json!({
+            "addendum_type": "CORRECTION",
+            "content": "Terapia: amoxicillina 1 g, non 500 mg",
+        }))
+        .unwrap();
+        assert!(request.validate().is_err());
+
+        let request = CreateVisitAddendumRequest {
+            reason: Some("Errore di trascrizione".to_string()),
+            ..request
+        };
+        assert!(request.validate().is_ok());
+
+        let request = CreateVisitAddendumRequest {
+            addendum_type: AddendumType::Addendum,
+            reason: None,
+            ..request
+        };
+        assert!(request.validate().is_ok());
+    }
+}
@@ -0,0 +1,193 @@
+/*!
+ * Visit Attachment Model
+ *
+ * Clinical photos, audio memos and files attached to a visit. The content
+ * is stored through `FileUploadService`, encrypted at rest; the caption is
+ * encrypted too. The kind follows the detected file type.
+ *
+ * Audio memos are transcribed by a background job when a transcription
+ * engine is configured; the transcript is split into a proposal for the
+ * visit's subjective and objective fields, which the user reviews and
+ * applies through the normal visit update.
+ */
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+/// Kind of visit attachment, from its MIME type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum VisitAttachmentKind {
+    /// Clinical photo (JPEG, PNG or WebP)
+    Photo,
+    /// Audio memo
+    Audio,
+    /// Any other document (PDF)
+    File,
+}
+
+impl VisitAttachmentKind {
+    /// Convert to database string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VisitAttachmentKind::Photo => "PHOTO",
+            VisitAttachmentKind::Audio => "AUDIO",
+            VisitAttachmentKind::File => "FILE",
+        }
+    }
+
+    /// Kind of a file with the given (detected) MIME type
+    pub fn from_mime_type(mime_type: &str) -> Self {
+        if mime_type.starts_with("image/") {
+            VisitAttachmentKind::Photo
+        } else if mime_type.starts_with("audio/") {
+            VisitAttachmentKind::Audio
+        } else {
+            VisitAttachmentKind::File
+        }
+    }
+}
+
+/// Progress of an audio memo's transcription (None for photos and files)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TranscriptionStatus {
+    Pending,
+    Processing,
+    Completed,
+    Failed,
+    /// The configured engine cannot read this audio format
+    Skipped,
+}
+
+impl TranscriptionStatus {
+    /// Convert to database string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TranscriptionStatus::Pending => "PENDING",
+            TranscriptionStatus::Processing => "PROCESSING",
+            TranscriptionStatus::Completed => "COMPLETED",
+            TranscriptionStatus::Failed => "FAILED",
+            TranscriptionStatus::Skipped => "SKIPPED",
+        }
+    }
+}
+
+/// Text of a transcribed memo proposed for the visit note, for the user to
+/// review (it is never applied automatically)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TranscriptProposal {
+    pub subjective: Option<String>,
+    pub objective: Option<String>,
+}
+
+/// Attachment row joined with its file's metadata
+#[derive(Debug, Clone, FromRow)]
+pub struct VisitAttachment {
+    pub id: Uuid,
+    pub visit_id: Uuid,
+    pub visit_date: NaiveDate,
+    pub patient_id: Uuid,
+    pub file_id: Uuid,
+    pub kind: String,
+    /// Encrypted
+    pub caption: Option<String>,
+    pub body_site: Option<String>,
+    pub transcription_status: Option<String>,
+    /// Encrypted
+    pub transcript: Option<String>,
+    /// Encrypted JSON (`TranscriptProposal`)
+    pub transcript_proposal: Option<String>,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+    // From uploaded_files
+    pub original_filename: String,
+    pub mime_type: String,
+    pub file_size_bytes: i64,
+}
+
+/// Attachment of a visit (API output)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VisitAttachmentResponse {
+    pub id: Uuid,
+    pub visit_id: Uuid,
+    pub patient_id: Uuid,
+    pub kind: String,
+    pub caption: Option<String>,
+    pub body_site: Option<String>,
+    pub original_filename: String,
+    pub mime_type: String,
+    pub file_size_bytes: i64,
+    /// Audio memos only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transcription_status: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transcript: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transcript_proposal: Option<TranscriptProposal>,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub download_url: String,
+}
+
+impl VisitAttachmentResponse {
+    /// Build the response from a row and its decrypted caption
+    ///
+    /// The transcript and proposal are left empty for the caller to decrypt.
+    pub fn new(attachment: VisitAttachment, caption: Option<String>) -> Self {
+        Self {
+            download_url: format!(
+                "/api/v1/visits/{}/attachments/{}/download",
+                attachment.visit_id, attachment.id
+            ),
+            id: attachment.id,
+            visit_id: attachment.visit_id,
+            patient_id: attachment.patient_id,
+            kind: attachment.kind,
+            caption,
+            body_site: attachment.body_site,
+            original_filename: attachment.original_filename,
+            mime_type: attachment.mime_type,
+            file_size_bytes: attachment.file_size_bytes,
+            transcription_status: attachment.transcription_status,
+            transcript: None,
+            transcript_proposal: None,
+            created_by: attachment.created_by,
+            created_at: attachment.created_at,
+        }
+    }
+}
+
+/// Metadata of a new visit attachment (multipart form fields next to `file`)
+#[derive(Debug, Clone, Default, Validate)]
+pub struct CreateVisitAttachmentRequest {
+    #[validate(length(max = 2000, message = "Caption must be at most 2000 characters"))]
+    pub caption: Option<String>,
+    #[validate(length(max = 100, message = "Body site must be at most 100 characters"))]
+    pub body_site: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kind_from_mime_type() {
+        assert_eq!(
+            VisitAttachmentKind::from_mime_type("image/jpeg"),
+            VisitAttachmentKind::Photo
+        );
+        assert_eq!(
+            VisitAttachmentKind::from_mime_type("audio/webm"),
+            VisitAttachmentKind::Audio
+        );
+        assert_eq!(
+            VisitAttachmentKind::from_mime_type("application/pdf"),
+            VisitAttachmentKind::File
+        );
+        assert_eq!(VisitAttachmentKind::Audio.as_str(), "AUDIO");
+    }
+}
@@ -0,0 +1,63 @@
+/*!
+ * Visit Calculation Model
+ *
+ * Clinical calculator results saved into a visit. The inputs and the result
+ * are stored encrypted as JSON.
+ */
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::services::clinical_calculators::CalculatorResult;
+use crate::utils::encryption::EncryptionKey;
+
+/// Calculation row (encrypted)
+#[derive(Debug, Clone, FromRow)]
+pub struct VisitCalculation {
+    pub id: Uuid,
+    pub visit_id: Uuid,
+    pub visit_date: NaiveDate,
+    pub patient_id: Uuid,
+    pub calculator: String,
+    pub inputs: String, // 🔒 Encrypted JSON
+    pub result: String, // 🔒 Encrypted JSON
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+}
+
+impl VisitCalculation {
+    /// Decrypt into the API representation
+    pub fn decrypt(&self, key: &EncryptionKey) -> Result<VisitCalculationResponse> {
+        let inputs = key.decrypt(&self.inputs)?;
+        let result = key.decrypt(&self.result)?;
+
+        Ok(VisitCalculationResponse {
+            id: self.id,
+            visit_id: self.visit_id,
+            visit_date: self.visit_date,
+            patient_id: self.patient_id,
+            calculator: self.calculator.clone(),
+            inputs: serde_json::from_str(&inputs).context("Invalid calculation inputs")?,
+            result: serde_json::from_str(&result).context("Invalid calculation result")?,
+            created_by: self.created_by,
+            created_at: self.created_at,
+        })
+    }
+}
+
+/// Calculation saved into a visit (API output)
+#[derive(Debug, Clone, Serialize)]
+pub struct VisitCalculationResponse {
+    pub id: Uuid,
+    pub visit_id: Uuid,
+    pub visit_date: NaiveDate,
+    pub patient_id: Uuid,
+    pub calculator: String,
+    pub inputs: serde_json::Value,
+    pub result: CalculatorResult,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+}
@@ -0,0 +1,84 @@
+/*!
+ * Visit Co-Signature Models
+ *
+ * Co-signature requirements (which users' visits must be countersigned, and
+ * by whom) and the queue of visits awaiting a supervisor's co-signature.
+ */
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+use super::VisitType;
+
+/// Co-signature requirement of a user (API output)
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct CosignRequirement {
+    pub user_id: Uuid,
+    pub user_name: String,
+    pub user_role: String,
+    pub supervisor_id: Uuid,
+    pub supervisor_name: String,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Request body for PUT /api/v1/visits/cosign-requirements/:user_id
+#[derive(Debug, Clone, Deserialize)]
+pub struct SetCosignRequirementRequest {
+    /// Doctor who countersigns the user's visits
+    pub supervisor_id: Uuid,
+}
+
+/// Request body for POST /api/v1/visits/:id/cosign/return
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct ReturnVisitRequest {
+    /// What the author should change (stored encrypted)
+    #[validate(length(min = 1, max = 2000, message = "Note must be 1-2000 characters"))]
+    pub note: String,
+}
+
+/// Query parameters for GET /api/v1/visits/cosign-queue
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CosignQueueQuery {
+    /// Admins only: visits waiting for this supervisor (default: all)
+    pub supervisor_id: Option<Uuid>,
+}
+
+/// Visit awaiting co-signature (patient names encrypted)
+#[derive(Debug, Clone, FromRow)]
+pub struct CosignQueueRow {
+    pub visit_id: Uuid,
+    pub visit_date: NaiveDate,
+    pub visit_type: VisitType,
+    pub patient_id: Uuid,
+    pub patient_first_name: Option<String>, // 🔒 Encrypted
+    pub patient_last_name: Option<String>,  // 🔒 Encrypted
+    pub provider_id: Uuid,
+    pub signed_by: Uuid,
+    pub signed_by_name: Option<String>,
+    pub signed_at: DateTime<Utc>,
+    pub cosign_supervisor_id: Uuid,
+    pub supervisor_name: Option<String>,
+}
+
+/// Visit awaiting co-signature (API output)
+#[derive(Debug, Clone, Serialize)]
+pub struct CosignQueueItem {
+    pub visit_id: Uuid,
+    pub visit_date: NaiveDate,
+    pub visit_type: VisitType,
+    pub patient_id: Uuid,
+    pub patient_name: Option<String>,
+    pub provider_id: Uuid,
+    pub signed_by: Uuid,
+    pub signed_by_name: Option<String>,
+    pub signed_at: DateTime<Utc>,
+    pub cosign_supervisor_id: Uuid,
+    pub supervisor_name: Option<String>,
+    /// Whole days since the visit was submitted
+    pub waiting_days: i64,
+}
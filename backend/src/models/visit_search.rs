@@ -0,0 +1,87 @@
+/*!
+ * Visit Note Search Model
+ *
+ * Full-text search over the narrative fields of visits. The notes are
+ * encrypted, so each visit is indexed on write into `visit_search_index`
+ * with keyed hashes of its words, and matches are highlighted after
+ * decryption.
+ */
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::{VisitStatus, VisitType};
+
+/// Query parameters for GET /api/v1/visits/search
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct VisitSearchQuery {
+    /// Words to find, all of them in the same visit (e.g. "carpal tunnel")
+    pub q: String,
+    pub patient_id: Option<Uuid>,
+    /// Default 20, max 100
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+impl VisitSearchQuery {
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(20).clamp(1, 100)
+    }
+
+    pub fn offset(&self) -> i64 {
+        self.offset.unwrap_or(0).max(0)
+    }
+}
+
+/// Matching passage of one field of a visit
+#[derive(Debug, Clone, Serialize)]
+pub struct VisitSearchHighlight {
+    /// Field name as in VisitResponse (e.g. "assessment")
+    pub field: String,
+    /// Text around the first match, HTML-escaped, matching words in `<mark>`
+    pub snippet: String,
+}
+
+/// Visit matching a search (API output)
+#[derive(Debug, Clone, Serialize)]
+pub struct VisitSearchHit {
+    pub visit_id: Uuid,
+    pub patient_id: Uuid,
+    pub patient_name: Option<String>,
+    pub provider_id: Uuid,
+    pub provider_name: Option<String>,
+    pub visit_date: NaiveDate,
+    pub visit_type: VisitType,
+    pub status: VisitStatus,
+    /// Relevance; matches in the chief complaint and assessment count most
+    pub rank: f32,
+    pub highlights: Vec<VisitSearchHighlight>,
+}
+
+/// Visit search results (API output)
+#[derive(Debug, Clone, Serialize)]
+pub struct VisitSearchResponse {
+    pub results: Vec<VisitSearchHit>,
+    pub total: i64,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_visit_search_query_bounds() {
+        let query = VisitSearchQuery {
+            q: "carpal tunnel".to_string(),
+            patient_id: None,
+            limit: Some(500),
+            offset: Some(-1),
+        };
+        assert_eq!(query.limit(), 100);
+        assert_eq!(query.offset(), 0);
+        assert_eq!(VisitSearchQuery::default().limit(), 20);
+    }
+}
@@ -0,0 +1,92 @@
+/*!
+ * Visit Survey Model
+ *
+ * After a COMPLETED appointment the notification scheduler emails the
+ * patient a short survey: how likely they are to recommend the practice,
+ * from 0 to 10 (the Net Promoter Score question), and an optional comment.
+ * The survey link holds a signed token naming the survey and the patient;
+ * the tokens are made and checked by `NotificationService`. A survey is
+ * answered once. Scores are aggregated into the dashboard; comments are
+ * encrypted.
+ */
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::Validate;
+
+/// Days a survey link stays valid after it is sent
+pub const VISIT_SURVEY_VALID_DAYS: i64 = 14;
+
+/// Appointments completed longer ago than this are not surveyed
+pub const VISIT_SURVEY_LOOKBACK_DAYS: i64 = 7;
+
+/// A patient is sent at most one survey in this many days
+pub const VISIT_SURVEY_PATIENT_INTERVAL_DAYS: i64 = 30;
+
+/// Net Promoter Score: the percentage of promoters (scores of 9 or 10)
+/// minus the percentage of detractors (0 to 6), from -100 to 100; `None`
+/// without responses
+pub fn net_promoter_score(promoters: i64, passives: i64, detractors: i64) -> Option<f64> {
+    let responses = promoters + passives + detractors;
+    if responses == 0 {
+        return None;
+    }
+    Some((promoters - detractors) as f64 / responses as f64 * 100.0)
+}
+
+/// Contents of a valid survey token
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VisitSurveyClaims {
+    pub survey_id: Uuid,
+    pub patient_id: Uuid,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Request body for POST /api/v1/surveys/respond
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct VisitSurveyAnswerRequest {
+    #[validate(length(min = 1, max = 256, message = "Invalid link"))]
+    pub token: String,
+    /// How likely the patient is to recommend the practice (0-10)
+    #[validate(range(min = 0, max = 10, message = "Score must be between 0 and 10"))]
+    pub score: i16,
+    #[validate(length(max = 2000, message = "Comment must be at most 2000 characters"))]
+    pub comment: Option<String>,
+}
+
+/// Survey after it was answered
+#[derive(Debug, Clone, Serialize)]
+pub struct VisitSurveyAnswerResponse {
+    pub score: i16,
+    pub responded_at: DateTime<Utc>,
+    pub message: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_net_promoter_score() {
+        assert_eq!(net_promoter_score(0, 0, 0), None);
+        assert_eq!(net_promoter_score(5, 3, 2), Some(30.0));
+        assert_eq!(net_promoter_score(0, 0, 4), Some(-100.0));
+        assert_eq!(net_promoter_score(2, 2, 0), Some(50.0));
+    }
+
+    #[test]
+    fn test_answer_request_validation() {
+        let request: VisitSurveyAnswerRequest =
+            serde_json::from_str(r#"{"token": "abc", "score": 9}"#).unwrap();
+        assert!(request.validate().is_ok());
+
+        let out_of_range: VisitSurveyAnswerRequest =
+            serde_json::from_str(r#"{"token": "abc", "score": 11}"#).unwrap();
+        assert!(out_of_range.validate().is_err());
+
+        let no_token: VisitSurveyAnswerRequest =
+            serde_json::from_str(r#"{"token": "", "score": 5}"#).unwrap();
+        assert!(no_token.validate().is_err());
+    }
+}
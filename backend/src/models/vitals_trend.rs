@@ -0,0 +1,215 @@
+/*!
+ * Vitals Trend Model
+ *
+ * Vital signs of a patient across visits, oldest first, for charting
+ * (weight, blood pressure, BMI over time). For patients under 20 at the
+ * visit, each point also carries the weight, height and BMI percentiles
+ * for age (WHO under 2 years, CDC from 2 to 20).
+ */
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::models::{patient::Gender, visit::VitalSigns};
+use crate::utils::growth_charts::{
+    growth_percentile, standard_for_age, GrowthMeasure, GrowthPercentile, GrowthSex, GrowthStandard,
+};
+
+/// Average month length in days, for ages in months
+const DAYS_PER_MONTH: f64 = 30.4375;
+
+/// Query parameters for GET /api/v1/patients/:id/vitals/trends
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct VitalsTrendQuery {
+    pub date_from: Option<NaiveDate>,
+    pub date_to: Option<NaiveDate>,
+}
+
+/// Growth percentiles at one visit
+#[derive(Debug, Clone, Serialize)]
+pub struct GrowthPercentiles {
+    pub standard: GrowthStandard,
+    pub weight_for_age: Option<GrowthPercentile>,
+    pub height_for_age: Option<GrowthPercentile>,
+    /// From 2 years only
+    pub bmi_for_age: Option<GrowthPercentile>,
+}
+
+/// Vital signs recorded at one visit (API output)
+#[derive(Debug, Clone, Serialize)]
+pub struct VitalsTrendPoint {
+    pub visit_id: Uuid,
+    pub visit_date: NaiveDate,
+    /// Age at the visit in whole months
+    pub age_months: Option<i32>,
+    pub weight_kg: Option<f32>,
+    pub height_cm: Option<f32>,
+    /// Recorded, or computed from weight and height
+    pub bmi: Option<f32>,
+    pub blood_pressure_systolic: Option<f32>,
+    pub blood_pressure_diastolic: Option<f32>,
+    pub heart_rate: Option<f32>,
+    pub respiratory_rate: Option<f32>,
+    pub temperature_celsius: Option<f32>,
+    pub oxygen_saturation: Option<f32>,
+    /// Only for pediatric visits of a patient recorded as M or F
+    pub growth: Option<GrowthPercentiles>,
+}
+
+impl VitalsTrendPoint {
+    /// Build the point for a visit, with percentiles if the patient was a
+    /// child at the visit
+    pub fn new(
+        visit_id: Uuid,
+        visit_date: NaiveDate,
+        mut vitals: VitalSigns,
+        date_of_birth: Option<NaiveDate>,
+        gender: &Gender,
+    ) -> Self {
+        if vitals.bmi.is_none() {
+            vitals.calculate_bmi();
+        }
+
+        let age_months = date_of_birth.map(|dob| age_in_months(dob, visit_date));
+        let sex = match gender {
+            Gender::M => Some(GrowthSex::Male),
+            Gender::F => Some(GrowthSex::Female),
+            Gender::Other | Gender::Unknown => None,
+        };
+        let growth = match (age_months, sex) {
+            (Some(age), Some(sex)) => growth_percentiles(&vitals, age, sex),
+            _ => None,
+        };
+
+        Self {
+            visit_id,
+            visit_date,
+            age_months: age_months.map(|age| age.floor() as i32),
+            weight_kg: vitals.weight_kg,
+            height_cm: vitals.height_cm,
+            bmi: vitals.bmi.map(|bmi| (bmi * 10.0).round() / 10.0),
+            blood_pressure_systolic: vitals.blood_pressure_systolic,
+            blood_pressure_diastolic: vitals.blood_pressure_diastolic,
+            heart_rate: vitals.heart_rate,
+            respiratory_rate: vitals.respiratory_rate,
+            temperature_celsius: vitals.temperature_celsius,
+            oxygen_saturation: vitals.oxygen_saturation,
+            growth,
+        }
+    }
+}
+
+/// Vitals trend of a patient (API output)
+#[derive(Debug, Clone, Serialize)]
+pub struct VitalsTrendResponse {
+    pub patient_id: Uuid,
+    /// True if the patient is under 20 today
+    pub pediatric: bool,
+    /// Visits with vital signs, oldest first
+    pub points: Vec<VitalsTrendPoint>,
+}
+
+/// Age in fractional months on a date
+pub fn age_in_months(date_of_birth: NaiveDate, on: NaiveDate) -> f64 {
+    (on - date_of_birth).num_days() as f64 / DAYS_PER_MONTH
+}
+
+fn growth_percentiles(
+    vitals: &VitalSigns,
+    age_months: f64,
+    sex: GrowthSex,
+) -> Option<GrowthPercentiles> {
+    let standard = standard_for_age(age_months)?;
+    let percentile = |measure, value: Option<f32>| {
+        value.and_then(|value| growth_percentile(sex, measure, age_months, value as f64))
+    };
+
+    let growth = GrowthPercentiles {
+        standard,
+        weight_for_age: percentile(GrowthMeasure::WeightForAge, vitals.weight_kg),
+        height_for_age: percentile(GrowthMeasure::HeightForAge, vitals.height_cm),
+        bmi_for_age: percentile(GrowthMeasure::BmiForAge, vitals.bmi),
+    };
+
+    if growth.weight_for_age.is_none()
+        && growth.height_for_age.is_none()
+        && growth.bmi_for_age.is_none()
+    {
+        return None;
+    }
+    Some(growth)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vitals(weight_kg: Option<f32>, height_cm: Option<f32>) -> VitalSigns {
+        VitalSigns {
+            blood_pressure_systolic: None,
+            blood_pressure_diastolic: None,
+            heart_rate: None,
+            respiratory_rate: None,
+            temperature_celsius: None,
+            weight_kg,
+            height_cm,
+            bmi: None,
+            oxygen_saturation: None,
+        }
+    }
+
+    #[test]
+    fn test_adult_point_has_no_growth() {
+        let dob = NaiveDate::from_ymd_opt(1970, 5, 1).unwrap();
+        let visit_date = NaiveDate::from_ymd_opt(2026, 4, 1).unwrap();
+        let point = VitalsTrendPoint::new(
+            Uuid::new_v4(),
+            visit_date,
+            vitals(Some(80.0), Some(180.0)),
+            Some(dob),
+            &Gender::M,
+        );
+
+        assert_eq!(point.bmi, Some(24.7));
+        assert!(point.growth.is_none());
+    }
+
+    #[test]
+    fn test_pediatric_point_percentiles() {
+        let dob = NaiveDate::from_ymd_opt(2025, 10, 1).unwrap();
+        let visit_date = NaiveDate::from_ymd_opt(2026, 4, 1).unwrap();
+        let point = VitalsTrendPoint::new(
+            Uuid::new_v4(),
+            visit_date,
+            vitals(Some(7.3), Some(65.7)),
+            Some(dob),
+            &Gender::F,
+        );
+
+        assert_eq!(point.age_months, Some(5));
+        let growth = point.growth.unwrap();
+        assert_eq!(growth.standard, GrowthStandard::Who);
+        let weight = growth.weight_for_age.unwrap();
+        assert!(weight.percentile > 40.0 && weight.percentile < 70.0);
+        assert!(growth.height_for_age.is_some());
+        // No BMI-for-age under 2 years
+        assert!(growth.bmi_for_age.is_none());
+    }
+
+    #[test]
+    fn test_no_growth_without_sex() {
+        let dob = NaiveDate::from_ymd_opt(2020, 1, 1).unwrap();
+        let visit_date = NaiveDate::from_ymd_opt(2026, 1, 1).unwrap();
+        let point = VitalsTrendPoint::new(
+            Uuid::new_v4(),
+            visit_date,
+            vitals(Some(20.0), Some(115.0)),
+            Some(dob),
+            &Gender::Unknown,
+        );
+
+        assert_eq!(point.age_months, Some(72));
+        assert!(point.growth.is_none());
+    }
+}
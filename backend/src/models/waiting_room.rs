@@ -0,0 +1,235 @@
+/*!
+ * Waiting Room Model
+ *
+ * Patients who arrive at the practice are checked in (status ARRIVED), which
+ * stamps `checked_in_at` with the arrival time. The waiting room queue lists the ARRIVED appointments of
+ * a day in the order they should be seen: by scheduled time, and among
+ * patients booked for the same time, by how long they have been waiting.
+ * Starting the visit (IN_PROGRESS) takes the patient out of the queue and
+ * stamps `visit_started_at`; the wait between the two feeds the
+ * productivity report.
+ */
+
+use chrono::{DateTime, NaiveDate, Utc};
+use chrono_tz::Europe::Rome;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use super::AppointmentType;
+
+/// Minutes between arrival and `now` (never negative)
+pub fn waiting_minutes(checked_in_at: DateTime<Utc>, now: DateTime<Utc>) -> i64 {
+    (now - checked_in_at).num_minutes().max(0)
+}
+
+/// Check that an arrival time fits the appointment: not in the future and
+/// on the day of the appointment (Europe/Rome)
+pub fn validate_arrival_time(
+    arrived_at: DateTime<Utc>,
+    scheduled_start: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> Result<(), &'static str> {
+    if arrived_at > now {
+        return Err("Arrival time cannot be in the future");
+    }
+    if arrived_at.with_timezone(&Rome).date_naive()
+        != scheduled_start.with_timezone(&Rome).date_naive()
+    {
+        return Err("Arrival time must be on the day of the appointment");
+    }
+    Ok(())
+}
+
+/// Request body for POST /api/v1/appointments/:id/check-in
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CheckInRequest {
+    /// When the patient arrived; now when omitted (for example when
+    /// recording an arrival after the fact)
+    #[serde(default)]
+    pub arrived_at: Option<DateTime<Utc>>,
+}
+
+/// Query parameters for GET /api/v1/appointments/queue
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct WaitingRoomQuery {
+    /// Only this provider's patients
+    pub provider_id: Option<Uuid>,
+    /// Day of the queue (Europe/Rome); defaults to today
+    pub date: Option<NaiveDate>,
+}
+
+/// ARRIVED appointment, as read for the queue
+#[derive(Debug, Clone, FromRow)]
+pub struct WaitingRoomRow {
+    pub appointment_id: Uuid,
+    pub patient_id: Uuid,
+    pub medical_record_number: String,
+    pub provider_id: Uuid,
+    #[sqlx(rename = "type")]
+    pub appointment_type: AppointmentType,
+    pub scheduled_start: DateTime<Utc>,
+    pub checked_in_at: DateTime<Utc>,
+}
+
+/// Patient waiting to be seen
+#[derive(Debug, Clone, Serialize)]
+pub struct WaitingRoomEntry {
+    /// Place in the queue, from 1
+    pub position: usize,
+    pub appointment_id: Uuid,
+    pub patient_id: Uuid,
+    /// Patient names are encrypted; the MRN identifies the patient here
+    pub medical_record_number: String,
+    pub provider_id: Uuid,
+    #[serde(rename = "type")]
+    pub appointment_type: AppointmentType,
+    pub scheduled_start: DateTime<Utc>,
+    pub checked_in_at: DateTime<Utc>,
+    pub waiting_minutes: i64,
+}
+
+/// Waiting room of a day
+#[derive(Debug, Clone, Serialize)]
+pub struct WaitingRoomQueue {
+    pub date: NaiveDate,
+    pub entries: Vec<WaitingRoomEntry>,
+    /// Average wait so far of the patients in the queue
+    pub average_waiting_minutes: Option<f64>,
+    pub longest_waiting_minutes: Option<i64>,
+}
+
+impl WaitingRoomQueue {
+    /// Build the queue from its rows, ordered by scheduled time and then by
+    /// waiting time (earliest arrival first)
+    pub fn from_rows(date: NaiveDate, mut rows: Vec<WaitingRoomRow>, now: DateTime<Utc>) -> Self {
+        rows.sort_by_key(|row| (row.scheduled_start, row.checked_in_at));
+
+        let entries: Vec<WaitingRoomEntry> = rows
+            .into_iter()
+            .enumerate()
+            .map(|(index, row)| WaitingRoomEntry {
+                position: index + 1,
+                waiting_minutes: waiting_minutes(row.checked_in_at, now),
+                appointment_id: row.appointment_id,
+                patient_id: row.patient_id,
+                medical_record_number: row.medical_record_number,
+                provider_id: row.provider_id,
+                appointment_type: row.appointment_type,
+                scheduled_start: row.scheduled_start,
+                checked_in_at: row.checked_in_at,
+            })
+            .collect();
+
+        let total_waiting: i64 = entries.iter().map(|e| e.waiting_minutes).sum();
+        let average_waiting_minutes = if entries.is_empty() {
+            None
+        } else {
+            Some(total_waiting as f64 / entries.len() as f64)
+        };
+        let longest_waiting_minutes = entries.iter().map(|e| e.waiting_minutes).max();
+
+        Self {
+            date,
+            entries,
+            average_waiting_minutes,
+            longest_waiting_minutes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn row(scheduled_start: DateTime<Utc>, checked_in_at: DateTime<Utc>) -> WaitingRoomRow {
+        WaitingRoomRow {
+            appointment_id: Uuid::new_v4(),
+            patient_id: Uuid::new_v4(),
+            medical_record_number: "MRN-2026-0001".to_string(),
+            provider_id: Uuid::new_v4(),
+            appointment_type: AppointmentType::FollowUp,
+            scheduled_start,
+            checked_in_at,
+        }
+    }
+
+    #[test]
+    fn test_waiting_minutes() {
+        let now = Utc::now();
+        assert_eq!(waiting_minutes(now - Duration::minutes(25), now), 25);
+        assert_eq!(waiting_minutes(now + Duration::minutes(5), now), 0);
+    }
+
+    #[test]
+    fn test_validate_arrival_time() {
+        // 10:00 and 10:30 in Rome
+        let scheduled_start: DateTime<Utc> = "2026-05-07T08:00:00Z".parse().unwrap();
+        let now = scheduled_start + Duration::minutes(30);
+
+        assert!(validate_arrival_time(now, scheduled_start, now).is_ok());
+        assert!(
+            validate_arrival_time(scheduled_start - Duration::hours(2), scheduled_start, now)
+                .is_ok()
+        );
+        assert_eq!(
+            validate_arrival_time(now + Duration::minutes(1), scheduled_start, now),
+            Err("Arrival time cannot be in the future")
+        );
+        assert_eq!(
+            validate_arrival_time(now - Duration::days(1), scheduled_start, now),
+            Err("Arrival time must be on the day of the appointment")
+        );
+    }
+
+    #[test]
+    fn test_check_in_request_arrival_is_optional() {
+        let request: CheckInRequest = serde_json::from_str("{}").unwrap();
+        assert_eq!(request.arrived_at, None);
+
+        let request: CheckInRequest =
+            serde_json::from_str(r#"{"arrived_at": "2026-05-07T07:18:00Z"}"#).unwrap();
+        assert!(request.arrived_at.is_some());
+    }
+
+    #[test]
+    fn test_queue_orders_by_appointment_time_then_wait() {
+        let now = Utc::now();
+        let nine = now - Duration::minutes(30);
+        let half_past_nine = now;
+
+        let late_booking = row(half_past_nine, now - Duration::minutes(40));
+        let arrived_second = row(nine, now - Duration::minutes(10));
+        let arrived_first = row(nine, now - Duration::minutes(20));
+        let expected = [
+            arrived_first.appointment_id,
+            arrived_second.appointment_id,
+            late_booking.appointment_id,
+        ];
+
+        let queue = WaitingRoomQueue::from_rows(
+            now.date_naive(),
+            vec![late_booking, arrived_second, arrived_first],
+            now,
+        );
+
+        let order: Vec<Uuid> = queue.entries.iter().map(|e| e.appointment_id).collect();
+        assert_eq!(order, expected);
+        assert_eq!(queue.entries[0].position, 1);
+        assert_eq!(queue.entries[2].position, 3);
+        assert_eq!(queue.entries[0].waiting_minutes, 20);
+        assert_eq!(queue.longest_waiting_minutes, Some(40));
+        assert_eq!(queue.average_waiting_minutes, Some(70.0 / 3.0));
+    }
+
+    #[test]
+    fn test_empty_queue() {
+        let now = Utc::now();
+        let queue = WaitingRoomQueue::from_rows(now.date_naive(), Vec::new(), now);
+
+        assert!(queue.entries.is_empty());
+        assert_eq!(queue.average_waiting_minutes, None);
+        assert_eq!(queue.longest_waiting_minutes, None);
+    }
+}
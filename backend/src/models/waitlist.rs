@@ -0,0 +1,336 @@
+/*!
+ * Waitlist Model
+ *
+ * Patients who want an earlier appointment wait on the waitlist, for one
+ * provider or any, with the length of appointment they need and the days
+ * they can come. When an appointment is cancelled while patients wait, its
+ * time becomes a waitlist slot, and the waitlist job offers the slot to the
+ * matching patients in the order they joined, a few at a time.
+ *
+ * Each offer is emailed with two links, to accept and to decline, holding a
+ * signed token naming the offer, the patient and the action. The first
+ * patient to accept gets the appointment; the other offers of the slot are
+ * superseded. When every offer of a slot was declined or has expired, the
+ * slot goes to the next patients. The tokens are made and checked by
+ * `NotificationService`.
+ */
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, Type};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::models::appointment::AppointmentType;
+
+/// Setting: minutes a patient has to answer an offer
+pub const WAITLIST_OFFER_EXPIRY_SETTING_KEY: &str = "waitlist.offer_expiry_minutes";
+
+/// Setting: patients offered a slot at the same time
+pub const WAITLIST_OFFERS_PER_ROUND_SETTING_KEY: &str = "waitlist.offers_per_round";
+
+/// Offers close this long before the slot starts, so that the patient who
+/// accepts has time to come; slots starting sooner are no longer offered
+pub const WAITLIST_OFFER_CUTOFF_MINUTES: i64 = 30;
+
+/// Most patients offered a slot at the same time
+pub const MAX_WAITLIST_OFFERS_PER_ROUND: i64 = 20;
+
+/// Waitlist settings (`waitlist.*`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WaitlistSettings {
+    pub offer_expiry_minutes: i64,
+    pub offers_per_round: i64,
+}
+
+impl Default for WaitlistSettings {
+    fn default() -> Self {
+        Self {
+            offer_expiry_minutes: 120,
+            offers_per_round: 3,
+        }
+    }
+}
+
+impl WaitlistSettings {
+    /// When an offer made at `now` for a slot starting at `slot_start`
+    /// expires: after the expiry time, but never later than the offer
+    /// cutoff of the slot
+    pub fn offer_expires_at(&self, now: DateTime<Utc>, slot_start: DateTime<Utc>) -> DateTime<Utc> {
+        (now + Duration::minutes(self.offer_expiry_minutes))
+            .min(slot_start - Duration::minutes(WAITLIST_OFFER_CUTOFF_MINUTES))
+    }
+}
+
+/// Whether a slot starting at `slot_start` may still be offered at `now`
+pub fn waitlist_slot_offerable(slot_start: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+    slot_start - Duration::minutes(WAITLIST_OFFER_CUTOFF_MINUTES) > now
+}
+
+/// Status of a patient on the waitlist
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[sqlx(type_name = "VARCHAR", rename_all = "SCREAMING_SNAKE_CASE")]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum WaitlistEntryStatus {
+    /// Waiting for a slot
+    Waiting,
+    /// Accepted an offer
+    Booked,
+    /// Taken off the list by staff
+    Cancelled,
+    /// The last day the patient could come has passed
+    Expired,
+}
+
+/// Status of a slot offered to a patient
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[sqlx(type_name = "VARCHAR", rename_all = "SCREAMING_SNAKE_CASE")]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum WaitlistOfferStatus {
+    /// Waiting for the patient's answer
+    Pending,
+    /// The patient got the appointment
+    Accepted,
+    Declined,
+    /// Not answered in time
+    Expired,
+    /// Another patient accepted first, or the slot is gone
+    Superseded,
+}
+
+/// What a waitlist offer link does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum WaitlistOfferAction {
+    Accept,
+    Decline,
+}
+
+impl WaitlistOfferAction {
+    /// Form used in tokens
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Accept => "accept",
+            Self::Decline => "decline",
+        }
+    }
+
+    /// Parse the form used in tokens
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "accept" => Some(Self::Accept),
+            "decline" => Some(Self::Decline),
+            _ => None,
+        }
+    }
+}
+
+/// Contents of a valid waitlist offer token
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WaitlistOfferClaims {
+    pub offer_id: Uuid,
+    pub patient_id: Uuid,
+    pub action: WaitlistOfferAction,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Patient on the waitlist
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct WaitlistEntry {
+    pub id: Uuid,
+    pub patient_id: Uuid,
+    /// Patient names are encrypted; the MRN identifies the patient here
+    pub medical_record_number: String,
+    /// `None`: any provider
+    pub provider_id: Option<Uuid>,
+    /// `None`: the type of the freed appointment
+    pub appointment_type: Option<AppointmentType>,
+    pub duration_minutes: i32,
+    pub earliest_date: Option<NaiveDate>,
+    pub latest_date: Option<NaiveDate>,
+    pub status: WaitlistEntryStatus,
+    pub booked_appointment_id: Option<Uuid>,
+    pub closed_at: Option<DateTime<Utc>>,
+    /// Slots offered to the patient so far
+    pub offers_sent: i64,
+    /// Expiry of the offer awaiting the patient's answer, if any
+    pub pending_offer_expires_at: Option<DateTime<Utc>>,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Slot offered to a waitlisted patient
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct WaitlistOffer {
+    pub id: Uuid,
+    pub waitlist_entry_id: Uuid,
+    pub patient_id: Uuid,
+    pub provider_id: Uuid,
+    pub slot_start: DateTime<Utc>,
+    pub slot_end: DateTime<Utc>,
+    pub status: WaitlistOfferStatus,
+    pub expires_at: DateTime<Utc>,
+    pub responded_at: Option<DateTime<Utc>>,
+    /// Appointment booked by accepting
+    pub appointment_id: Option<Uuid>,
+    pub notification_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Request body for POST /api/v1/appointments/waitlist
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct CreateWaitlistEntryRequest {
+    pub patient_id: Uuid,
+    /// Omit for any provider
+    pub provider_id: Option<Uuid>,
+    /// Omit to take the type of the freed appointment
+    #[serde(rename = "type")]
+    pub appointment_type: Option<AppointmentType>,
+    /// Defaults to 30
+    #[validate(range(min = 15, max = 480, message = "Duration must be between 15 and 480 minutes"))]
+    pub duration_minutes: Option<i32>,
+    pub earliest_date: Option<NaiveDate>,
+    pub latest_date: Option<NaiveDate>,
+}
+
+impl CreateWaitlistEntryRequest {
+    /// Check the days the patient can come, as of `today`
+    pub fn validate_dates(&self, today: NaiveDate) -> Result<(), &'static str> {
+        if let (Some(earliest), Some(latest)) = (self.earliest_date, self.latest_date) {
+            if earliest > latest {
+                return Err("earliest_date must not be after latest_date");
+            }
+        }
+        if self.latest_date.is_some_and(|latest| latest < today) {
+            return Err("latest_date must not be in the past");
+        }
+        Ok(())
+    }
+}
+
+/// Query parameters for GET /api/v1/appointments/waitlist
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct WaitlistQuery {
+    /// Defaults to WAITING
+    pub status: Option<WaitlistEntryStatus>,
+    /// Entries for this provider, including those for any provider
+    pub provider_id: Option<Uuid>,
+    pub patient_id: Option<Uuid>,
+}
+
+/// Offer after a link was used, as seen by the patient
+#[derive(Debug, Clone)]
+pub struct WaitlistOfferOutcome {
+    pub offer_id: Uuid,
+    pub status: WaitlistOfferStatus,
+    pub slot_start: DateTime<Utc>,
+    pub slot_end: DateTime<Utc>,
+    pub provider_name: String,
+    pub appointment_id: Option<Uuid>,
+    /// Whether the link changed the offer (`false` when it was already
+    /// answered, expired or superseded)
+    pub changed: bool,
+}
+
+/// Request body for POST /api/v1/waitlist-offers/respond
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct WaitlistOfferRequest {
+    #[validate(length(min = 1, max = 256, message = "Invalid link"))]
+    pub token: String,
+}
+
+/// Offer after a link was used
+#[derive(Debug, Clone, Serialize)]
+pub struct WaitlistOfferResponse {
+    pub action: WaitlistOfferAction,
+    pub status: WaitlistOfferStatus,
+    pub slot_start: DateTime<Utc>,
+    pub slot_end: DateTime<Utc>,
+    pub provider_name: String,
+    pub message: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(earliest: Option<&str>, latest: Option<&str>) -> CreateWaitlistEntryRequest {
+        CreateWaitlistEntryRequest {
+            patient_id: Uuid::new_v4(),
+            provider_id: None,
+            appointment_type: None,
+            duration_minutes: None,
+            earliest_date: earliest.map(|d| d.parse().unwrap()),
+            latest_date: latest.map(|d| d.parse().unwrap()),
+        }
+    }
+
+    #[test]
+    fn test_action_round_trip() {
+        for action in [WaitlistOfferAction::Accept, WaitlistOfferAction::Decline] {
+            assert_eq!(WaitlistOfferAction::from_str(action.as_str()), Some(action));
+        }
+        assert_eq!(WaitlistOfferAction::from_str("ACCEPT"), None);
+    }
+
+    #[test]
+    fn test_offer_expires_before_the_slot() {
+        let settings = WaitlistSettings::default();
+        let now: DateTime<Utc> = "2026-05-08T08:00:00Z".parse().unwrap();
+
+        let next_week = now + Duration::days(7);
+        assert_eq!(
+            settings.offer_expires_at(now, next_week),
+            now + Duration::minutes(120)
+        );
+
+        let in_an_hour = now + Duration::hours(1);
+        assert_eq!(
+            settings.offer_expires_at(now, in_an_hour),
+            in_an_hour - Duration::minutes(WAITLIST_OFFER_CUTOFF_MINUTES)
+        );
+    }
+
+    #[test]
+    fn test_slot_offerable_until_cutoff() {
+        let now: DateTime<Utc> = "2026-05-08T08:00:00Z".parse().unwrap();
+
+        assert!(waitlist_slot_offerable(now + Duration::hours(1), now));
+        assert!(!waitlist_slot_offerable(
+            now + Duration::minutes(WAITLIST_OFFER_CUTOFF_MINUTES),
+            now
+        ));
+        assert!(!waitlist_slot_offerable(now - Duration::hours(1), now));
+    }
+
+    #[test]
+    fn test_validate_dates() {
+        let today: NaiveDate = "2026-05-08".parse().unwrap();
+
+        assert!(request(None, None).validate_dates(today).is_ok());
+        assert!(request(Some("2026-05-10"), Some("2026-05-20"))
+            .validate_dates(today)
+            .is_ok());
+        assert_eq!(
+            request(Some("2026-05-20"), Some("2026-05-10")).validate_dates(today),
+            Err("earliest_date must not be after latest_date")
+        );
+        assert_eq!(
+            request(None, Some("2026-05-07")).validate_dates(today),
+            Err("latest_date must not be in the past")
+        );
+    }
+
+    #[test]
+    fn test_statuses_serialize_in_screaming_case() {
+        assert_eq!(
+            serde_json::to_value(WaitlistOfferStatus::Superseded).unwrap(),
+            "SUPERSEDED"
+        );
+        assert_eq!(
+            serde_json::from_value::<WaitlistEntryStatus>("WAITING".into()).unwrap(),
+            WaitlistEntryStatus::Waiting
+        );
+    }
+}
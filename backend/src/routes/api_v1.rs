@@ -5,49 +5,103 @@
  */
 
 use axum::{
+    extract::DefaultBodyLimit,
     middleware,
-    routing::{get, post, put},
+    routing::{delete, get, post, put},
     Router,
 };
 
 use crate::handlers::auth::AppState;
 use crate::handlers::{
-    bulk_update_settings, cancel_appointment, cancel_prescription, check_availability,
-    complete_prescription, create_appointment, create_custom_medication, create_diagnosis,
-    create_patient, create_prescription, create_prescription_template, create_visit,
+    autocomplete_patients, bulk_update_settings, cancel_appointment, cancel_prescription, captcha_config_handler, change_password_handler,
+    check_availability, complete_prescription, create_appointment, create_custom_medication,
+    create_diagnosis, create_patient, create_prescription, create_prescription_template, create_visit,
     create_visit_template, delete_diagnosis, delete_patient, delete_prescription,
     delete_prescription_template, delete_visit, delete_visit_template, discontinue_prescription,
-    export_report, get_appointment, get_appointment_report, get_daily_schedule,
-    get_dashboard_report, get_diagnosis, get_diagnosis_report, get_monthly_schedule, get_patient,
-    get_patient_diagnoses, get_patient_prescriptions, get_patient_report, get_patient_statistics,
-    get_patient_visits, get_prescription, get_prescription_template, get_productivity_report,
-    get_revenue_report, get_setting, get_settings_by_group, get_visit, get_visit_diagnoses,
-    get_visit_prescriptions, get_visit_statistics, get_visit_template, get_visit_version,
-    get_weekly_schedule, hold_prescription, list_appointments, list_groups, list_patients,
-    list_prescription_templates, list_prescriptions, list_settings, list_visit_templates,
-    list_visit_versions, list_visits, lock_visit, login_handler, logout_handler,
-    mfa_enroll_handler, mfa_setup_handler, reactivate_patient, refresh_token_handler,
-    reset_setting, restore_visit_version, resume_prescription, search_icd10, search_medications,
-    search_patients, sign_visit, update_appointment, update_diagnosis, update_patient,
-    update_prescription, update_prescription_template, update_setting, update_visit,
-    update_visit_template,
+    export_quality_indicators, export_report, export_research_dataset, get_appointment,
+    get_appointment_heatmap, get_appointment_report,
+    get_daily_schedule, get_dashboard_report, get_diagnosis, get_diagnosis_report,
+    get_monthly_schedule, get_patient, get_patient_chart, get_patient_diagnoses, get_patient_prescriptions,
+    get_patient_report, get_patient_statistics, get_patient_visits, get_prescription,
+    get_prescription_template, get_productivity_report, get_quality_indicators, get_revenue_report, get_setting,
+    get_settings_by_group, get_visit, get_visit_diagnoses, get_visit_prescriptions,
+    get_visit_statistics, get_visit_template, get_visit_version, get_weekly_schedule,
+    hold_prescription, introspect_handler, jwks_handler, list_appointments, list_groups,
+    list_patients, list_prescription_templates, list_prescriptions, list_settings,
+    list_trusted_devices_handler, list_visit_templates, list_visit_versions, list_visits, lock_visit, login_handler,
+    logout_handler, mfa_enroll_handler, mfa_setup_handler, reactivate_patient,
+    refresh_token_handler, reset_setting, restore_visit_version, resume_prescription,
+    revoke_all_trusted_devices_handler, revoke_token_handler, revoke_trusted_device_handler,
+    search_icd10, search_medications, search_patients, search_visit_notes, sign_visit, update_appointment, update_diagnosis, update_patient, update_prescription,
+    update_prescription_template, update_setting, update_visit, update_visit_template,
 };
+use crate::handlers::appointment_links;
 use crate::handlers::audit_logs;
+use crate::handlers::branding;
+use crate::handlers::batch;
+use crate::handlers::calculators;
+use crate::handlers::consents;
+use crate::handlers::delegations;
 use crate::handlers::drug_interactions;
+use crate::handlers::e_prescriptions;
 use crate::handlers::files;
 use crate::handlers::holidays;
+use crate::handlers::imaging;
+use crate::handlers::impersonation;
+use crate::handlers::labs;
 use crate::handlers::notifications;
+use crate::handlers::calendar_feeds;
+use crate::handlers::overbooking;
+use crate::handlers::resources;
+use crate::handlers::patient_allergies;
+use crate::handlers::patient_attachments;
+use crate::handlers::patient_communications;
+use crate::handlers::patient_erasure;
+use crate::handlers::patient_exports;
+use crate::handlers::patient_merge;
+use crate::handlers::patient_messages;
+use crate::handlers::patient_photos;
+use crate::handlers::patient_cohorts;
+use crate::handlers::patient_problems;
+use crate::handlers::prescriptions;
+use crate::handlers::recalls;
+use crate::handlers::referrals;
+use crate::handlers::report_subscriptions;
+use crate::handlers::rate_limits;
+use crate::handlers::retention;
+use crate::handlers::medication_sync;
 use crate::handlers::system_health;
+use crate::handlers::televisits;
+use crate::handlers::waiting_room;
+use crate::handlers::waitlist;
+use crate::handlers::trash;
+use crate::handlers::visit_addenda;
+use crate::handlers::visit_attachments;
+use crate::handlers::visit_cosign;
+use crate::handlers::visit_surveys;
+use crate::handlers::vitals;
 use crate::handlers::working_hours;
+use crate::middleware::audit::skip_domain_events;
 use crate::middleware::auth::jwt_auth_middleware;
+use crate::middleware::field_redaction::field_redaction_middleware;
+#[cfg(feature = "rbac")]
+use crate::middleware::authorization::delegation_window_middleware;
+use crate::middleware::ip_allowlist::admin_ip_allowlist_middleware;
+use crate::middleware::rate_limit::login_throttle_middleware;
 use crate::middleware::request_context::request_context_middleware;
+use crate::models::uploaded_file::{MAX_FILE_SIZE, MAX_PATIENT_PHOTO_SIZE};
 
 #[cfg(feature = "rbac")]
-use crate::handlers::users;
+use crate::handlers::{authorization, invitations, users};
 
 #[cfg(feature = "pdf-export")]
 use crate::handlers::documents;
 
+/// Field policy resources applied to each route group (see `middleware::field_redaction`)
+const PATIENT_REDACTED_RESOURCES: &[&str] = &["patients", "visits", "diagnoses"];
+const VISIT_REDACTED_RESOURCES: &[&str] = &["visits", "diagnoses"];
+const DIAGNOSIS_REDACTED_RESOURCES: &[&str] = &["diagnoses"];
+
 /// Create API v1 routes
 ///
 /// # Arguments
@@ -60,63 +114,492 @@ use crate::handlers::documents;
 pub fn create_api_v1_routes(state: AppState) -> Router {
     // Authentication routes (no auth middleware required)
     let auth_routes = Router::new()
-        .route("/login", post(login_handler))
+        .route(
+            "/login",
+            post(login_handler).layer(middleware::from_fn_with_state(
+                state.clone(),
+                login_throttle_middleware,
+            )),
+        )
         .route("/refresh", post(refresh_token_handler))
-        .route("/logout", post(logout_handler));
+        .route("/logout", post(logout_handler))
+        .route("/jwks", get(jwks_handler))
+        .route("/captcha", get(captcha_config_handler));
 
-    // MFA routes - require authentication (AUTH-VULN-03, AUTH-VULN-14)
+    // Invitation acceptance routes (no auth middleware - the invitee has no account yet;
+    // the invitation token in the request body authorizes the call)
+    #[cfg(feature = "rbac")]
+    let auth_routes = auth_routes
+        .route("/invitations/lookup", post(invitations::lookup_invitation))
+        .route("/invitations/mfa-setup", post(invitations::invitation_mfa_setup))
+        .route("/invitations/accept", post(invitations::accept_invitation));
+
+    // MFA, password and token management routes - require authentication (AUTH-VULN-03, AUTH-VULN-14)
     let mfa_routes = Router::new()
         .route("/mfa/setup", post(mfa_setup_handler))
         .route("/mfa/enroll", post(mfa_enroll_handler))
+        .route("/change-password", post(change_password_handler))
+        .route("/introspect", post(introspect_handler))
+        .route("/revoke", post(revoke_token_handler))
+        .route(
+            "/trusted-devices",
+            get(list_trusted_devices_handler).delete(revoke_all_trusted_devices_handler),
+        )
+        .route(
+            "/trusted-devices/{id}",
+            axum::routing::delete(revoke_trusted_device_handler),
+        )
+        .route("/impersonation/end", post(impersonation::end_impersonation))
         .layer(middleware::from_fn_with_state(
             state.clone(),
             jwt_auth_middleware,
         ));
 
-    // User management routes (RBAC feature) - requires authentication
+    // Permission introspection routes (RBAC feature) - require authentication
+    #[cfg(feature = "rbac")]
+    let mfa_routes = mfa_routes.merge(
+        Router::new()
+            .route("/permissions", get(authorization::get_my_permissions))
+            .route("/can", post(authorization::check_my_permission))
+            .layer(middleware::from_fn_with_state(
+                state.clone(),
+                jwt_auth_middleware,
+            )),
+    );
+
+    // User management routes (RBAC feature) - requires authentication and an allowed client IP
     #[cfg(feature = "rbac")]
     let user_routes = Router::new()
         .route("/", post(users::create_user).get(users::list_users))
+        .route(
+            "/invitations",
+            post(invitations::create_invitation).get(invitations::list_invitations),
+        )
+        .route(
+            "/invitations/{id}",
+            axum::routing::delete(invitations::revoke_invitation),
+        )
+        .route("/invitations/{id}/resend", post(invitations::resend_invitation))
         .route("/{id}", get(users::get_user).put(users::update_user))
         .route("/{id}/activate", post(users::activate_user))
         .route("/{id}/deactivate", post(users::deactivate_user))
         .route("/{id}/role", post(users::assign_role))
         .route("/{id}/reset-password", post(users::reset_password))
         .route("/{id}/reset-mfa", post(users::reset_mfa))
+        .route("/{id}/locum", post(users::set_locum))
+        .route("/{id}/impersonate", post(impersonation::start_impersonation))
+        .route("/impersonations", get(impersonation::list_impersonation_sessions))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            jwt_auth_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            admin_ip_allowlist_middleware,
+        ));
+
+    // Authorization policy management routes - requires authentication (ADMIN only) and an allowed client IP
+    #[cfg(feature = "rbac")]
+    let authorization_routes = Router::new()
+        .route(
+            "/policies",
+            get(authorization::list_policies)
+                .post(authorization::add_policy)
+                .delete(authorization::remove_policy),
+        )
+        .route(
+            "/role-assignments",
+            post(authorization::add_role_assignment).delete(authorization::remove_role_assignment),
+        )
+        .route("/reload", post(authorization::reload_policies))
         .layer(middleware::from_fn_with_state(
             state.clone(),
             jwt_auth_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            admin_ip_allowlist_middleware,
         ));
 
     // Patient management routes - requires authentication
     let patient_routes = Router::new()
         .route("/", post(create_patient).get(list_patients))
         .route("/search", get(search_patients))
+        .route("/autocomplete", get(autocomplete_patients))
         .route("/statistics", get(get_patient_statistics))
         .route("/{id}", get(get_patient).put(update_patient).delete(delete_patient))
         .route("/{id}/reactivate", post(reactivate_patient))
+        .route("/{id}/chart", get(get_patient_chart))
         .route("/{id}/visits", get(get_patient_visits))
         .route("/{id}/diagnoses", get(get_patient_diagnoses))
         .route("/{id}/prescriptions", get(get_patient_prescriptions))
+        .route(
+            "/{id}/photo",
+            get(patient_photos::get_patient_photo)
+                .put(patient_photos::upload_patient_photo)
+                .delete(patient_photos::delete_patient_photo)
+                // Room for the multipart framing around the photo
+                .layer(DefaultBodyLimit::max(MAX_PATIENT_PHOTO_SIZE + 64 * 1024)),
+        )
+        .route("/{id}/photo/thumbnail", get(patient_photos::get_patient_photo_thumbnail))
+        .route(
+            "/{id}/allergies",
+            get(patient_allergies::list_patient_allergies)
+                .post(patient_allergies::create_patient_allergy),
+        )
+        .route(
+            "/{id}/allergies/{allergy_id}",
+            get(patient_allergies::get_patient_allergy)
+                .put(patient_allergies::update_patient_allergy)
+                .delete(patient_allergies::delete_patient_allergy),
+        )
+        .route(
+            "/{id}/problems",
+            get(patient_problems::list_patient_problems)
+                .post(patient_problems::create_patient_problem),
+        )
+        .route(
+            "/{id}/problems/{problem_id}",
+            get(patient_problems::get_patient_problem)
+                .put(patient_problems::update_patient_problem)
+                .delete(patient_problems::delete_patient_problem),
+        )
+        .route(
+            "/{id}/problems/{problem_id}/visits",
+            post(patient_problems::link_patient_problem_visit),
+        )
+        .route(
+            "/{id}/problems/{problem_id}/visits/{visit_id}",
+            axum::routing::delete(patient_problems::unlink_patient_problem_visit),
+        )
+        .route(
+            "/{id}/lab-orders",
+            get(labs::list_lab_orders).post(labs::create_lab_order),
+        )
+        .route(
+            "/{id}/lab-orders/{order_id}",
+            get(labs::get_lab_order)
+                .put(labs::update_lab_order)
+                .delete(labs::delete_lab_order),
+        )
+        .route(
+            "/{id}/lab-results",
+            get(labs::list_lab_results).post(labs::create_lab_result),
+        )
+        .route("/{id}/lab-results/trend", get(labs::get_lab_result_trend))
+        .route(
+            "/{id}/lab-results/{result_id}",
+            get(labs::get_lab_result)
+                .put(labs::update_lab_result)
+                .delete(labs::delete_lab_result),
+        )
+        .route("/{id}/vitals/trends", get(vitals::get_vitals_trends))
+        .route(
+            "/{id}/imaging-orders",
+            get(imaging::list_imaging_orders).post(imaging::create_imaging_order),
+        )
+        .route(
+            "/{id}/imaging-orders/{order_id}",
+            get(imaging::get_imaging_order)
+                .put(imaging::update_imaging_order)
+                .delete(imaging::delete_imaging_order),
+        )
+        .route(
+            "/{id}/referrals",
+            get(referrals::list_referrals).post(referrals::create_referral),
+        )
+        .route(
+            "/{id}/referrals/{referral_id}",
+            get(referrals::get_referral)
+                .put(referrals::update_referral)
+                .delete(referrals::delete_referral),
+        )
+        .route(
+            "/{id}/communications",
+            get(patient_communications::list_patient_communications)
+                .post(patient_communications::create_patient_communication),
+        )
+        .route(
+            "/{id}/communications/{communication_id}",
+            get(patient_communications::get_patient_communication),
+        )
+        .route(
+            "/{id}/communications/{communication_id}/annotations",
+            post(patient_communications::annotate_patient_communication),
+        )
+        .route(
+            "/{id}/message-threads",
+            get(patient_messages::list_patient_message_threads)
+                .post(patient_messages::create_message_thread),
+        )
+        .route(
+            "/{id}/attachments",
+            get(patient_attachments::list_patient_attachments)
+                .post(patient_attachments::create_patient_attachment)
+                .layer(DefaultBodyLimit::max(MAX_FILE_SIZE + 64 * 1024)),
+        )
+        .route(
+            "/{id}/attachments/{attachment_id}",
+            get(patient_attachments::get_patient_attachment)
+                .put(patient_attachments::update_patient_attachment)
+                .delete(patient_attachments::delete_patient_attachment),
+        )
+        .route(
+            "/{id}/attachments/{attachment_id}/download",
+            get(patient_attachments::download_patient_attachment),
+        )
+        .route(
+            "/{id}/attachments/{attachment_id}/ocr",
+            post(patient_attachments::requeue_patient_attachment_ocr),
+        )
+        .route(
+            "/{id}/access-log",
+            get(audit_logs::get_patient_access_log).layer(middleware::from_fn_with_state(
+                state.clone(),
+                admin_ip_allowlist_middleware,
+            )),
+        )
         .route(
             "/{id}/notification-preferences",
             get(notifications::get_patient_preferences).put(notifications::update_patient_preferences),
         )
+        .route(
+            "/{id}/consents",
+            get(consents::list_patient_consents).post(consents::record_patient_consent),
+        )
+        .route("/{id}/consents/{consent_id}", get(consents::get_patient_consent))
+        .route(
+            "/{id}/consents/{consent_id}/withdraw",
+            post(consents::withdraw_patient_consent),
+        )
+        .layer(middleware::from_fn_with_state(
+            PATIENT_REDACTED_RESOURCES,
+            field_redaction_middleware,
+        ));
+
+    // Locum accounts may only reach patients and appointments while a delegation is active
+    #[cfg(feature = "rbac")]
+    let patient_routes = patient_routes.layer(middleware::from_fn_with_state(
+        state.clone(),
+        delegation_window_middleware,
+    ));
+
+    let patient_routes = patient_routes.layer(middleware::from_fn_with_state(
+        state.clone(),
+        jwt_auth_middleware,
+    ));
+
+    // GDPR subject access exports, erasure requests and duplicate merges - requires authentication (ADMIN only) and an allowed client IP
+    let patient_routes = patient_routes.merge(
+        Router::new()
+            .route("/{id}/export", post(patient_exports::create_patient_export))
+            .route("/{id}/exports", get(patient_exports::list_patient_exports))
+            .route(
+                "/{id}/exports/{export_id}",
+                get(patient_exports::get_patient_export),
+            )
+            .route(
+                "/{id}/exports/{export_id}/download",
+                get(patient_exports::download_patient_export),
+            )
+            .route(
+                "/{id}/erasure-requests",
+                post(patient_erasure::create_erasure_request),
+            )
+            .route("/{id}/merge/{merge_id}", post(patient_merge::merge_patients))
+            .layer(middleware::from_fn_with_state(
+                state.clone(),
+                jwt_auth_middleware,
+            ))
+            .layer(middleware::from_fn_with_state(
+                state.clone(),
+                admin_ip_allowlist_middleware,
+            )),
+    );
+
+    // GDPR erasure request review - requires authentication (ADMIN only) and an allowed client IP
+    let erasure_request_routes = Router::new()
+        .route("/", get(patient_erasure::list_erasure_requests))
+        .route("/{id}", get(patient_erasure::get_erasure_request))
+        .route("/{id}/approve", post(patient_erasure::approve_erasure_request))
+        .route("/{id}/reject", post(patient_erasure::reject_erasure_request))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            jwt_auth_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            admin_ip_allowlist_middleware,
+        ));
+
+    // Consent texts - requires authentication (publishing is ADMIN only)
+    let consent_text_routes = Router::new()
+        .route(
+            "/",
+            get(consents::list_consent_texts).post(consents::create_consent_text),
+        )
+        .route("/{id}", get(consents::get_consent_text))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            jwt_auth_middleware,
+        ));
+
+    // Recycle bin - requires authentication (per-type RBAC checked in handlers)
+    let trash_routes = Router::new()
+        .route("/", get(trash::list_trash))
+        .route("/{entity_type}/{id}/restore", post(trash::restore_trash_item))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            jwt_auth_middleware,
+        ));
+
+    // Data retention rules - requires authentication (ADMIN only) and an allowed client IP
+    let retention_routes = Router::new()
+        .route("/", get(retention::list_retention_rules))
+        .route("/run", post(retention::run_retention))
+        .route("/runs", get(retention::list_retention_runs))
+        .route("/{id}", put(retention::update_retention_rule))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            jwt_auth_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            admin_ip_allowlist_middleware,
+        ));
+
+    // Rate limits and per-user quotas - requires authentication (ADMIN only) and an allowed client IP
+    let rate_limit_routes = Router::new()
+        .route("/", get(rate_limits::get_rate_limits))
+        .route(
+            "/users/{user_id}",
+            get(rate_limits::get_user_rate_limit)
+                .put(rate_limits::set_user_rate_limit)
+                .delete(rate_limits::delete_user_rate_limit),
+        )
         .layer(middleware::from_fn_with_state(
             state.clone(),
             jwt_auth_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            admin_ip_allowlist_middleware,
+        ));
+
+    // AIFA medication sync - requires authentication (ADMIN only) and an allowed client IP
+    let medication_sync_routes = Router::new()
+        .route("/run", post(medication_sync::run_medication_sync))
+        .route("/runs", get(medication_sync::list_medication_sync_runs))
+        .route("/runs/{id}", get(medication_sync::get_medication_sync_run))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            jwt_auth_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            admin_ip_allowlist_middleware,
         ));
 
     // Appointment management routes - requires authentication
     let appointment_routes = Router::new()
         .route("/", post(create_appointment).get(list_appointments))
         .route("/availability", get(check_availability))
+        .route("/queue", get(waiting_room::get_waiting_room_queue))
+        .route(
+            "/overbooking-policies",
+            get(overbooking::list_overbooking_policies),
+        )
+        .route(
+            "/overbooking-policies/{provider_id}",
+            put(overbooking::set_overbooking_policy).delete(overbooking::delete_overbooking_policy),
+        )
+        .route(
+            "/resources",
+            get(resources::list_resources).post(resources::create_resource),
+        )
+        .route("/resources/{id}", put(resources::update_resource))
+        .route(
+            "/calendar-feed",
+            get(calendar_feeds::get_calendar_feed_settings)
+                .post(calendar_feeds::create_calendar_feed)
+                .put(calendar_feeds::update_calendar_feed)
+                .delete(calendar_feeds::delete_calendar_feed),
+        )
+        .route("/waitlist", get(waitlist::list_waitlist).post(waitlist::create_waitlist_entry))
+        .route("/waitlist/{id}", delete(waitlist::cancel_waitlist_entry))
+        .route("/waitlist/{id}/offers", get(waitlist::list_waitlist_entry_offers))
         .route("/statistics", get(crate::handlers::appointments::get_statistics))
         .route("/schedule/daily", get(get_daily_schedule))
         .route("/schedule/weekly", get(get_weekly_schedule))
         .route("/schedule/monthly", get(get_monthly_schedule))
         .route("/{id}", get(get_appointment).put(update_appointment))
         .route("/{id}/cancel", post(cancel_appointment))
+        .route("/{id}/check-in", post(waiting_room::check_in_appointment))
+        .route(
+            "/{id}/televisit",
+            get(televisits::get_televisit).post(televisits::create_televisit),
+        )
+        .route("/{id}/televisit/join", post(televisits::join_televisit))
+        .route("/{id}/televisit/leave", post(televisits::leave_televisit));
+
+    #[cfg(feature = "rbac")]
+    let appointment_routes = appointment_routes.layer(middleware::from_fn_with_state(
+        state.clone(),
+        delegation_window_middleware,
+    ));
+
+    let appointment_routes = appointment_routes.layer(middleware::from_fn_with_state(
+        state.clone(),
+        jwt_auth_middleware,
+    ));
+
+    // Batch operations on appointments - requires authentication (per-operation RBAC checked in handler)
+    let batch_routes = Router::new().route("/", post(batch::execute_batch));
+
+    #[cfg(feature = "rbac")]
+    let batch_routes = batch_routes.layer(middleware::from_fn_with_state(
+        state.clone(),
+        delegation_window_middleware,
+    ));
+
+    let batch_routes = batch_routes.layer(middleware::from_fn_with_state(
+        state.clone(),
+        jwt_auth_middleware,
+    ));
+
+    // Lab test catalog - requires authentication
+    let lab_test_routes = Router::new()
+        .route("/", get(labs::list_lab_catalog))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            jwt_auth_middleware,
+        ));
+
+    // Practice-wide referral follow-up - requires authentication
+    let referral_routes = Router::new()
+        .route("/overdue", get(referrals::list_overdue_referrals))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            jwt_auth_middleware,
+        ));
+
+    // Clinical calculators - requires authentication
+    let calculator_routes = Router::new()
+        .route("/bmi", post(calculators::calculate_bmi_score))
+        .route("/egfr", post(calculators::calculate_egfr_score))
+        .route("/cha2ds2-vasc", post(calculators::calculate_cha2ds2_vasc_score))
+        .route("/has-bled", post(calculators::calculate_has_bled_score))
+        .route("/score2", post(calculators::calculate_score2_risk))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            jwt_auth_middleware,
+        ));
+
+    // Access delegation routes - requires authentication
+    let delegation_routes = Router::new()
+        .route("/", post(delegations::create_delegation).get(delegations::list_delegations))
+        .route("/{id}", get(delegations::get_delegation))
+        .route("/{id}/revoke", post(delegations::revoke_delegation))
         .layer(middleware::from_fn_with_state(
             state.clone(),
             jwt_auth_middleware,
@@ -126,14 +609,57 @@ pub fn create_api_v1_routes(state: AppState) -> Router {
     let visit_routes = Router::new()
         .route("/", post(create_visit).get(list_visits))
         .route("/statistics", get(get_visit_statistics))
+        .route("/search", get(search_visit_notes))
+        .route("/cosign-queue", get(visit_cosign::get_cosign_queue))
+        .route("/cosign-requirements", get(visit_cosign::list_cosign_requirements))
+        .route(
+            "/cosign-requirements/{user_id}",
+            put(visit_cosign::set_cosign_requirement).delete(visit_cosign::delete_cosign_requirement),
+        )
         .route("/{id}", get(get_visit).put(update_visit).delete(delete_visit))
         .route("/{id}/sign", post(sign_visit))
         .route("/{id}/lock", post(lock_visit))
+        .route("/{id}/cosign", post(visit_cosign::cosign_visit))
+        .route("/{id}/cosign/return", post(visit_cosign::return_visit_for_revision))
         .route("/{id}/diagnoses", get(get_visit_diagnoses))
         .route("/{id}/prescriptions", get(get_visit_prescriptions))
         .route("/{id}/versions", get(list_visit_versions))
         .route("/{id}/versions/{version_number}", get(get_visit_version))
         .route("/{id}/versions/{version_number}/restore", post(restore_visit_version))
+        .route(
+            "/{id}/calculations",
+            get(calculators::list_visit_calculations).post(calculators::save_visit_calculation),
+        )
+        .route(
+            "/{id}/calculations/{calculation_id}",
+            delete(calculators::delete_visit_calculation),
+        )
+        .route(
+            "/{id}/addenda",
+            get(visit_addenda::list_visit_addenda).post(visit_addenda::create_visit_addendum),
+        )
+        .route(
+            "/{id}/attachments",
+            get(visit_attachments::list_visit_attachments)
+                .post(visit_attachments::create_visit_attachment)
+                .layer(DefaultBodyLimit::max(MAX_FILE_SIZE + 64 * 1024)),
+        )
+        .route(
+            "/{id}/attachments/{attachment_id}",
+            delete(visit_attachments::delete_visit_attachment),
+        )
+        .route(
+            "/{id}/attachments/{attachment_id}/download",
+            get(visit_attachments::download_visit_attachment),
+        )
+        .route(
+            "/{id}/attachments/{attachment_id}/transcription",
+            post(visit_attachments::requeue_visit_attachment_transcription),
+        )
+        .layer(middleware::from_fn_with_state(
+            VISIT_REDACTED_RESOURCES,
+            field_redaction_middleware,
+        ))
         .layer(middleware::from_fn_with_state(
             state.clone(),
             jwt_auth_middleware,
@@ -144,6 +670,11 @@ pub fn create_api_v1_routes(state: AppState) -> Router {
         .route("/", post(create_diagnosis))
         .route("/icd10/search", get(search_icd10))
         .route("/{id}", get(get_diagnosis).put(update_diagnosis).delete(delete_diagnosis))
+        .route("/{id}/promote", post(patient_problems::promote_diagnosis_to_problem))
+        .layer(middleware::from_fn_with_state(
+            DIAGNOSIS_REDACTED_RESOURCES,
+            field_redaction_middleware,
+        ))
         .layer(middleware::from_fn_with_state(
             state.clone(),
             jwt_auth_middleware,
@@ -152,18 +683,96 @@ pub fn create_api_v1_routes(state: AppState) -> Router {
     // Prescription management routes - requires authentication
     let prescription_routes = Router::new()
         .route("/", get(list_prescriptions).post(create_prescription))
+        .route("/allergy-check", post(prescriptions::check_prescription_allergies))
+        .route("/overdue-refills", get(prescriptions::list_overdue_refills))
+        .route(
+            "/overdue-refills/reminders",
+            post(prescriptions::send_refill_reminders),
+        )
         .route("/medications/search", get(search_medications))
         .route("/medications/custom", post(create_custom_medication))
+        .route(
+            "/medications/favorites",
+            get(prescriptions::list_medication_favorites)
+                .post(prescriptions::create_medication_favorite),
+        )
+        .route(
+            "/medications/favorites/{favorite_id}",
+            put(prescriptions::update_medication_favorite)
+                .delete(prescriptions::delete_medication_favorite),
+        )
+        .route("/medications/quick-pick", get(prescriptions::get_medication_quick_pick))
         .route("/{id}", get(get_prescription).put(update_prescription).delete(delete_prescription))
         .route("/{id}/discontinue", post(discontinue_prescription))
         .route("/{id}/cancel", post(cancel_prescription))
         .route("/{id}/hold", post(hold_prescription))
         .route("/{id}/resume", post(resume_prescription))
         .route("/{id}/complete", post(complete_prescription))
-        .layer(middleware::from_fn_with_state(
-            state.clone(),
-            jwt_auth_middleware,
-        ));
+        .route("/{id}/refill", post(prescriptions::record_prescription_refill))
+        .route(
+            "/{id}/e-prescription",
+            get(e_prescriptions::get_e_prescription).post(e_prescriptions::submit_e_prescription),
+        )
+        .route(
+            "/{id}/e-prescription/cancel",
+            post(e_prescriptions::cancel_e_prescription),
+        );
+
+    // NRE promemoria, SSN print and print batches (PDF export feature)
+    #[cfg(feature = "pdf-export")]
+    let prescription_routes = prescription_routes
+        .route(
+            "/{id}/e-prescription/promemoria",
+            post(e_prescriptions::generate_nre_promemoria),
+        )
+        .route("/{id}/ssn-print", post(prescriptions::generate_ssn_prescription))
+        .route(
+            "/print-batches",
+            post(prescriptions::create_prescription_print_batch),
+        )
+        .route(
+            "/print-batches/{batch_id}",
+            get(prescriptions::get_prescription_print_batch),
+        )
+        .route(
+            "/print-batches/{batch_id}/download",
+            get(prescriptions::download_prescription_print_batch),
+        );
+
+    let prescription_routes = prescription_routes.layer(middleware::from_fn_with_state(
+        state.clone(),
+        jwt_auth_middleware,
+    ));
+
+    // Sistema TS status callbacks - authenticated by the callback secret, not a JWT
+    let e_prescription_callback_routes = Router::new()
+        .route("/callback", post(e_prescriptions::e_prescription_callback));
+
+    // Appointment reminder links - authenticated by the signed link token, not a JWT
+    // The handler audits the change itself; the request carries the link token
+    let appointment_link_routes = Router::new()
+        .route("/respond", post(appointment_links::respond_to_appointment_link))
+        .route_layer(middleware::from_fn(skip_domain_events));
+
+    // Waitlist offer links - authenticated by the signed offer token, not a JWT
+    let waitlist_offer_routes = Router::new()
+        .route("/respond", post(waitlist::respond_to_waitlist_offer))
+        .route_layer(middleware::from_fn(skip_domain_events));
+
+    // Visit survey links - authenticated by the signed survey token, not a JWT
+    let visit_survey_routes = Router::new()
+        .route("/respond", post(visit_surveys::respond_to_visit_survey))
+        .route_layer(middleware::from_fn(skip_domain_events));
+
+    // Televisit links - authenticated by the signed televisit token, not a JWT
+    let televisit_link_routes = Router::new()
+        .route("/join", post(televisits::patient_join_televisit))
+        .route("/leave", post(televisits::patient_leave_televisit))
+        .route_layer(middleware::from_fn(skip_domain_events));
+
+    // Calendar feeds - authenticated by the token in the URL, not a JWT
+    let calendar_feed_routes =
+        Router::new().route("/{file_name}", get(calendar_feeds::get_calendar_feed));
 
     // Visit template routes - requires authentication
     let visit_template_routes = Router::new()
@@ -186,12 +795,97 @@ pub fn create_api_v1_routes(state: AppState) -> Router {
     // Reporting & Analytics routes - requires authentication
     let report_routes = Router::new()
         .route("/appointments", get(get_appointment_report))
+        .route("/appointments/heatmap", get(get_appointment_heatmap))
         .route("/patients", get(get_patient_report))
         .route("/diagnoses", get(get_diagnosis_report))
         .route("/productivity", get(get_productivity_report))
         .route("/revenue", get(get_revenue_report))
+        .route("/quality-indicators", get(get_quality_indicators))
+        .route("/quality-indicators/export", get(export_quality_indicators))
         .route("/dashboard", get(get_dashboard_report))
         .route("/export", post(export_report))
+        .route("/research-export", post(export_research_dataset))
+        .route(
+            "/subscriptions",
+            get(report_subscriptions::list_report_subscriptions)
+                .post(report_subscriptions::create_report_subscription),
+        )
+        .route(
+            "/subscriptions/{id}",
+            get(report_subscriptions::get_report_subscription)
+                .put(report_subscriptions::update_report_subscription)
+                .delete(report_subscriptions::delete_report_subscription),
+        )
+        .route("/subscriptions/{id}/send", post(report_subscriptions::send_report_subscription))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            jwt_auth_middleware,
+        ));
+
+    // Patient cohort routes - requires authentication (ADMIN, DOCTOR)
+    let cohort_routes = Router::new()
+        .route(
+            "/",
+            get(patient_cohorts::list_cohorts).post(patient_cohorts::create_cohort),
+        )
+        .route("/preview", post(patient_cohorts::preview_cohort_criteria))
+        .route(
+            "/{id}",
+            get(patient_cohorts::get_cohort)
+                .put(patient_cohorts::update_cohort)
+                .delete(patient_cohorts::delete_cohort),
+        )
+        .route("/{id}/preview", get(patient_cohorts::preview_cohort))
+        .route("/{id}/notifications", post(patient_cohorts::send_cohort_notifications));
+
+    #[cfg(feature = "pdf-export")]
+    let cohort_routes = cohort_routes.route(
+        "/{id}/documents",
+        post(patient_cohorts::generate_cohort_documents),
+    );
+
+    let cohort_routes = cohort_routes.layer(middleware::from_fn_with_state(
+        state.clone(),
+        jwt_auth_middleware,
+    ));
+
+    // Recall routes - requires authentication (rules: ADMIN, DOCTOR; opt-outs: all staff)
+    let recall_routes = Router::new()
+        .route(
+            "/rules",
+            get(recalls::list_recall_rules).post(recalls::create_recall_rule),
+        )
+        .route(
+            "/rules/{id}",
+            get(recalls::get_recall_rule)
+                .put(recalls::update_recall_rule)
+                .delete(recalls::delete_recall_rule),
+        )
+        .route("/rules/{id}/run", post(recalls::run_recall_rule))
+        .route("/rules/{id}/runs", get(recalls::list_recall_runs))
+        .route("/rules/{id}/entries", get(recalls::list_recall_entries))
+        .route("/rules/{id}/outcomes", get(recalls::get_recall_outcome))
+        .route("/entries/{id}/close", post(recalls::close_recall_entry))
+        .route(
+            "/opt-outs",
+            get(recalls::list_recall_opt_outs).post(recalls::create_recall_opt_out),
+        )
+        .route("/opt-outs/{id}", delete(recalls::delete_recall_opt_out))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            jwt_auth_middleware,
+        ));
+
+    // Secure messaging routes - requires authentication (ADMIN, DOCTOR, NURSE)
+    let message_thread_routes = Router::new()
+        .route("/", get(patient_messages::list_message_inbox))
+        .route("/unread-count", get(patient_messages::get_message_unread_count))
+        .route(
+            "/{id}",
+            get(patient_messages::get_message_thread).put(patient_messages::update_message_thread),
+        )
+        .route("/{id}/messages", post(patient_messages::create_patient_message))
+        .route("/{id}/read", post(patient_messages::mark_message_thread_read))
         .layer(middleware::from_fn_with_state(
             state.clone(),
             jwt_auth_middleware,
@@ -202,6 +896,7 @@ pub fn create_api_v1_routes(state: AppState) -> Router {
     let document_template_routes = Router::new()
         .route("/", post(documents::create_document_template).get(documents::list_document_templates))
         .route("/default", get(documents::get_default_document_template))
+        .route("/variables", get(documents::get_template_variable_catalog))
         .route("/{id}", get(documents::get_document_template).put(documents::update_document_template).delete(documents::delete_document_template))
         .layer(middleware::from_fn_with_state(
             state.clone(),
@@ -216,6 +911,7 @@ pub fn create_api_v1_routes(state: AppState) -> Router {
         .route("/statistics", get(documents::get_document_statistics))
         .route("/{id}", get(documents::get_generated_document).delete(documents::delete_generated_document))
         .route("/{id}/download", get(documents::download_document))
+        .route("/{id}/retry", post(documents::retry_generated_document))
         .route("/{id}/sign", post(documents::sign_document))
         .route("/{id}/deliver", post(documents::deliver_document))
         .layer(middleware::from_fn_with_state(
@@ -223,7 +919,43 @@ pub fn create_api_v1_routes(state: AppState) -> Router {
             jwt_auth_middleware,
         ));
 
-    // System settings routes - requires authentication
+    // Patient chart print bundle, lab requisitions, imaging referral and
+    // referral letters (PDF export feature) - requires authentication
+    #[cfg(feature = "pdf-export")]
+    let patient_routes = patient_routes.merge(
+        Router::new()
+            .route("/{id}/print-bundle", post(documents::create_print_bundle))
+            .route("/{id}/export/pdf", get(documents::export_patient_chart_pdf))
+            .route(
+                "/{id}/lab-orders/{order_id}/requisition",
+                post(labs::generate_lab_requisition),
+            )
+            .route(
+                "/{id}/imaging-orders/{order_id}/referral-letter",
+                post(imaging::generate_imaging_referral),
+            )
+            .route(
+                "/{id}/referrals/{referral_id}/letter",
+                post(referrals::generate_referral_letter),
+            )
+            .layer(middleware::from_fn_with_state(
+                state.clone(),
+                jwt_auth_middleware,
+            )),
+    );
+
+    // Visit clinical summary PDF (PDF export feature) - requires authentication
+    #[cfg(feature = "pdf-export")]
+    let visit_routes = visit_routes.merge(
+        Router::new()
+            .route("/{id}/export/pdf", get(documents::export_visit_summary_pdf))
+            .layer(middleware::from_fn_with_state(
+                state.clone(),
+                jwt_auth_middleware,
+            )),
+    );
+
+    // System settings routes - requires authentication and an allowed client IP
     let settings_routes = Router::new()
         .route("/", get(list_settings))
         .route("/groups", get(list_groups))
@@ -234,6 +966,10 @@ pub fn create_api_v1_routes(state: AppState) -> Router {
         .layer(middleware::from_fn_with_state(
             state.clone(),
             jwt_auth_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            admin_ip_allowlist_middleware,
         ));
 
     // Working hours routes - requires authentication
@@ -261,17 +997,24 @@ pub fn create_api_v1_routes(state: AppState) -> Router {
             jwt_auth_middleware,
         ));
 
-    // Audit logs routes - requires authentication (ADMIN only via RBAC)
+    // Audit logs routes - requires authentication (ADMIN only via RBAC) and an allowed client IP
     let audit_logs_routes = Router::new()
         .route("/", get(audit_logs::list_audit_logs))
         .route("/statistics", get(audit_logs::get_statistics))
         .route("/export", get(audit_logs::export_audit_logs))
+        .route("/export/stream", get(audit_logs::stream_audit_logs_export))
+        .route("/activity-report", get(audit_logs::get_user_activity_report))
         .route("/filter-options", get(audit_logs::get_filter_options))
+        .route("/verify", get(audit_logs::verify_audit_chain))
         .route("/user/{user_id}/activity", get(audit_logs::get_user_activity))
         .route("/{id}", get(audit_logs::get_audit_log))
         .layer(middleware::from_fn_with_state(
             state.clone(),
             jwt_auth_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            admin_ip_allowlist_middleware,
         ));
 
     // System health & status routes - requires authentication (ADMIN only via RBAC)
@@ -280,6 +1023,13 @@ pub fn create_api_v1_routes(state: AppState) -> Router {
         .route("/info", get(system_health::get_system_info))
         .route("/storage", get(system_health::get_storage_stats))
         .route("/backup-status", get(system_health::get_backup_status))
+        .route("/alerts", get(system_health::list_system_alerts))
+        .route(
+            "/alerts/{id}/acknowledge",
+            post(system_health::acknowledge_system_alert),
+        )
+        .route("/telemetry/preview", get(system_health::get_telemetry_preview))
+        .route("/telemetry/reports", get(system_health::list_telemetry_reports))
         .layer(middleware::from_fn_with_state(
             state.clone(),
             jwt_auth_middleware,
@@ -306,6 +1056,23 @@ pub fn create_api_v1_routes(state: AppState) -> Router {
             jwt_auth_middleware,
         ));
 
+    // Branding routes - metadata and asset images are public (login page, emails)
+    let public_branding_routes = Router::new()
+        .route("/", get(branding::get_branding))
+        .route("/assets/{asset}", get(branding::serve_branding_asset));
+
+    // Branding management routes - requires authentication (ADMIN only)
+    let branding_admin_routes = Router::new()
+        .route("/", put(branding::update_branding))
+        .route(
+            "/assets/{asset}",
+            post(branding::upload_branding_asset).delete(branding::delete_branding_asset),
+        )
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            jwt_auth_middleware,
+        ));
+
     // Drug interactions routes - requires authentication
     let drug_interactions_routes = Router::new()
         .route("/check", post(drug_interactions::check_interactions))
@@ -313,6 +1080,8 @@ pub fn create_api_v1_routes(state: AppState) -> Router {
         .route("/check-new-for-patient", post(drug_interactions::check_new_medication_for_patient))
         .route("/patient/{patient_id}", get(drug_interactions::check_patient_interactions))
         .route("/statistics", get(drug_interactions::get_statistics))
+        // Checks are read-only despite using POST
+        .route_layer(middleware::from_fn(skip_domain_events))
         .layer(middleware::from_fn_with_state(
             state.clone(),
             jwt_auth_middleware,
@@ -337,17 +1106,44 @@ pub fn create_api_v1_routes(state: AppState) -> Router {
 
     // Combine all v1 routes
     let mut router = Router::new()
-        .nest("/auth", auth_routes.merge(mfa_routes))
+        // Auth requests and responses carry credentials; handlers audit logins themselves
+        .nest(
+            "/auth",
+            auth_routes
+                .merge(mfa_routes)
+                .route_layer(middleware::from_fn(skip_domain_events)),
+        )
         .nest("/patients", patient_routes)
+        .nest("/erasure-requests", erasure_request_routes)
+        .nest("/consent-texts", consent_text_routes)
+        .nest("/retention-rules", retention_routes)
+        .nest("/rate-limits", rate_limit_routes)
+        .nest("/trash", trash_routes)
+        .nest("/medication-sync", medication_sync_routes)
         .nest("/appointments", appointment_routes)
+        .nest("/appointment-links", appointment_link_routes)
+        .nest("/waitlist-offers", waitlist_offer_routes)
+        .nest("/surveys", visit_survey_routes)
+        .nest("/televisit", televisit_link_routes)
+        .nest("/calendar", calendar_feed_routes)
+        .nest("/batch", batch_routes)
+        .nest("/delegations", delegation_routes)
+        .nest("/lab-tests", lab_test_routes)
+        .nest("/referrals", referral_routes)
+        .nest("/calculators", calculator_routes)
         .nest("/visits", visit_routes)
         .nest("/diagnoses", diagnosis_routes)
         .nest("/prescriptions", prescription_routes)
+        .nest("/e-prescriptions", e_prescription_callback_routes)
         .nest("/visit-templates", visit_template_routes)
         .nest("/prescription-templates", prescription_template_routes)
         .nest("/reports", report_routes)
+        .nest("/cohorts", cohort_routes)
+        .nest("/recalls", recall_routes)
+        .nest("/message-threads", message_thread_routes)
         .nest("/settings", settings_routes)
         .nest("/settings/logo", logo_routes)
+        .nest("/branding", public_branding_routes.merge(branding_admin_routes))
         .nest("/working-hours", working_hours_routes)
         .nest("/holidays", holidays_routes)
         .nest("/audit-logs", audit_logs_routes)
@@ -358,7 +1154,9 @@ pub fn create_api_v1_routes(state: AppState) -> Router {
 
     #[cfg(feature = "rbac")]
     {
-        router = router.nest("/users", user_routes);
+        router = router
+            .nest("/users", user_routes)
+            .nest("/authorization", authorization_routes);
     }
 
     #[cfg(feature = "pdf-export")]
@@ -370,7 +1168,7 @@ pub fn create_api_v1_routes(state: AppState) -> Router {
 
     // Apply global middleware layers to all routes.
     // Layers are applied in reverse order: last added = outermost = runs first on request.
-    // Execution order: error_redaction → request_context → audit → per-route auth → handler
+    // Execution order: request_context → rate_limit → error_redaction → audit → per-route auth → handler
     router
         // Audit logging: logs all HTTP requests to audit_logs table (HIPAA compliance)
         // Extracts user ID directly from JWT header, so works for all routes
@@ -378,12 +1176,18 @@ pub fn create_api_v1_routes(state: AppState) -> Router {
             state.clone(),
             crate::middleware::audit::audit_middleware,
         ))
-        // Request context: extracts IP address, user agent, and generates request ID
-        .layer(middleware::from_fn(request_context_middleware))
-        // Error redaction: replaces detailed extractor errors with generic JSON (outermost)
+        // Error redaction: replaces detailed extractor errors with generic JSON
         .layer(middleware::from_fn(
             crate::middleware::error_redaction::redact_extractor_errors,
         ))
+        // Rate limiting: per-user/per-IP quotas, adds X-RateLimit-* headers to every response
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            crate::middleware::rate_limit::rate_limit_middleware,
+        ))
+        // Request context: extracts IP address and user agent, assigns the correlation ID
+        // (outermost, so every response - including redacted errors - carries it)
+        .layer(middleware::from_fn(request_context_middleware))
         .with_state(state)
 }
 
@@ -411,6 +1215,7 @@ mod tests {
             refresh_secret: "test_refresh_secret_key_minimum_32_characters_long".to_string(),
             access_token_expiry: 1800,
             refresh_token_expiry: 604800,
+            key_ring: None,
         };
 
         let security_config = SecurityConfig {
@@ -418,6 +1223,7 @@ mod tests {
             session_timeout: 1800,
             max_failed_login_attempts: 5,
             lockout_duration: 900,
+            captcha: None,
         };
 
         // Note: This will fail if database is not available
@@ -439,9 +1245,16 @@ mod tests {
             pool: pool.clone(),
             auth_service: AuthService::new(jwt_config, security_config.clone()),
             session_manager: crate::middleware::session_timeout::SessionManager::new(security_config.session_timeout),
+            token_denylist: crate::middleware::token_denylist::TokenDenylist::new(),
+            login_throttle: crate::middleware::rate_limit::LoginThrottle::new(),
+            rate_limiter: crate::middleware::rate_limit::RateLimitLayer::new(),
+            admin_ip_allowlist: crate::middleware::ip_allowlist::AdminIpAllowlist::default(),
             encryption_key: None, // Not needed for auth routes test
             email_service: None,  // Not needed for routes test
+            sistema_ts: None,
+            video_room_provider: None,
             settings_service: Arc::new(SettingsService::new(pool)),
+            reference_cache: crate::services::ReferenceCache::memory(),
             start_time: std::time::SystemTime::now(),
             environment: "test".to_string(),
             #[cfg(feature = "rbac")]
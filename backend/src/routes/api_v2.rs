@@ -0,0 +1,26 @@
+/*!
+ * API v2 Routes
+ *
+ * v2 serves the v1 routes with every JSON response in one envelope
+ * (`data`, `error.code`, `error.details`, `meta.pagination`); see
+ * `middleware::api_envelope`. v1 stays unchanged while clients migrate.
+ */
+
+use axum::{middleware, Router};
+
+use crate::handlers::auth::AppState;
+use crate::middleware::api_envelope::api_v2_envelope;
+use crate::routes::create_api_v1_routes;
+
+/// Create API v2 routes
+///
+/// # Arguments
+///
+/// * `state` - Application state containing database pool and services
+///
+/// # Returns
+///
+/// Configured router for API v2
+pub fn create_api_v2_routes(state: AppState) -> Router {
+    create_api_v1_routes(state).layer(middleware::from_fn(api_v2_envelope))
+}
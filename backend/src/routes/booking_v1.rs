@@ -0,0 +1,52 @@
+/*!
+ * Online Booking v1 Routes
+ *
+ * The public self-booking API at `/api/booking/v1`. It shares no routes or
+ * middleware with the staff API and needs no token: booking requests are
+ * protected by a CAPTCHA, email verification and the bulk rate limit tier.
+ */
+
+use axum::{
+    middleware,
+    routing::{get, post},
+    Router,
+};
+
+use crate::handlers::auth::AppState;
+use crate::handlers::booking;
+use crate::middleware::request_context::request_context_middleware;
+
+/// Create online booking v1 routes
+///
+/// # Arguments
+///
+/// * `state` - Application state containing database pool and services
+///
+/// # Returns
+///
+/// Configured router for the online booking API
+pub fn create_booking_v1_routes(state: AppState) -> Router {
+    // GET /options - Bookable appointment types and doctors
+    // GET /slots - Free slots for an appointment type
+    // POST /requests - Request a slot
+    // POST /requests/verify - Verify a request
+    let booking_routes = Router::new()
+        .route("/options", get(booking::get_booking_options))
+        .route("/slots", get(booking::list_booking_slots))
+        .route("/requests", post(booking::create_booking_request))
+        .route("/requests/verify", post(booking::verify_booking_request));
+
+    // Booking handlers write their own audit entries (the audit middleware
+    // only covers the staff API).
+    // Execution order: request_context → rate_limit → error_redaction → handler
+    booking_routes
+        .layer(middleware::from_fn(
+            crate::middleware::error_redaction::redact_extractor_errors,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            crate::middleware::rate_limit::rate_limit_middleware,
+        ))
+        .layer(middleware::from_fn(request_context_middleware))
+        .with_state(state)
+}
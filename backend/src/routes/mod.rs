@@ -5,5 +5,11 @@
  */
 
 pub mod api_v1;
+pub mod api_v2;
+pub mod booking_v1;
+pub mod portal_v1;
 
 pub use api_v1::create_api_v1_routes;
+pub use api_v2::create_api_v2_routes;
+pub use booking_v1::create_booking_v1_routes;
+pub use portal_v1::create_portal_v1_routes;
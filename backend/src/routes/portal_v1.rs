@@ -0,0 +1,74 @@
+/*!
+ * Patient Portal v1 Routes
+ *
+ * The patient-facing API at `/api/portal/v1`. It shares no routes or
+ * middleware with the staff API: requests are authenticated with portal
+ * tokens by `portal_auth_middleware`, and staff tokens are rejected.
+ */
+
+use axum::{
+    middleware,
+    routing::{get, post, put},
+    Router,
+};
+
+use crate::handlers::auth::AppState;
+use crate::handlers::patient_portal;
+use crate::middleware::portal_auth::portal_auth_middleware;
+use crate::middleware::request_context::request_context_middleware;
+
+/// Create patient portal v1 routes
+///
+/// # Arguments
+///
+/// * `state` - Application state containing database pool and services
+///
+/// # Returns
+///
+/// Configured router for the patient portal API
+pub fn create_portal_v1_routes(state: AppState) -> Router {
+    // Sign-in routes (public)
+    // POST /auth/login-link - Email a one-time sign-in link
+    // POST /auth/session - Exchange the link for a portal token
+    let public_auth_routes = Router::new()
+        .route(
+            "/login-link",
+            post(patient_portal::request_portal_login_link),
+        )
+        .route("/session", post(patient_portal::start_portal_session));
+
+    // Routes for the signed-in patient - require a portal token
+    let patient_routes = Router::new()
+        .route("/auth/logout", post(patient_portal::portal_logout))
+        .route("/me", get(patient_portal::get_portal_profile))
+        .route("/me/contact", put(patient_portal::update_portal_contact))
+        .route(
+            "/appointments/upcoming",
+            get(patient_portal::list_portal_upcoming_appointments),
+        )
+        .route("/documents", get(patient_portal::list_portal_documents))
+        .route(
+            "/documents/{id}/download",
+            get(patient_portal::download_portal_document),
+        )
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            portal_auth_middleware,
+        ));
+
+    // Portal handlers write their own audit entries (the audit middleware
+    // only covers the staff API).
+    // Execution order: request_context → rate_limit → error_redaction → portal auth → handler
+    Router::new()
+        .nest("/auth", public_auth_routes)
+        .merge(patient_routes)
+        .layer(middleware::from_fn(
+            crate::middleware::error_redaction::redact_extractor_errors,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            crate::middleware::rate_limit::rate_limit_middleware,
+        ))
+        .layer(middleware::from_fn(request_context_middleware))
+        .with_state(state)
+}
@@ -7,19 +7,40 @@
  * - Recurring appointments
  * - Status workflow management
  * - Audit logging
+ *
+ * The reason, notes and cancellation reason are encrypted; appointments
+ * saved before they were are encrypted by a background job, or when they
+ * are next updated or cancelled.
  */
 
+use crate::db::rls::{
+    apply_rls_context, begin_portal_transaction, begin_with_rls, SYSTEM_ROLE, SYSTEM_USER_ID,
+};
+use crate::models::working_hours::EffectiveWorkingHours;
 use crate::models::{
-    Appointment, AppointmentDto, AppointmentSearchFilter, AppointmentStatistics, AuditAction, AuditLog, CreateAuditLog,
-    CreateAppointmentRequest, EntityType, RecurringPattern, RequestContext, TimeSlot,
-    UpdateAppointmentRequest,
+    parse_appointment_buffers, AlternativeSlot, Appointment, AppointmentBuffers, AppointmentDto,
+    AppointmentLinkAction, AppointmentLinkClaims, AppointmentLinkOutcome, AppointmentSearchFilter,
+    AppointmentStatistics, AppointmentStatus, AppointmentType, AuditAction, AuditLog,
+    CreateAuditLog, CreateAppointmentRequest, EntityType, NotificationType, ProviderAvailability,
+    RecurringPattern, RequestContext, SchedulingConflict, SchedulingConflictKind, TimeSlot, UpdateAppointmentRequest,
+    mark_busy_resources, validate_arrival_time, APPOINTMENT_BUFFERS_SETTING,
 };
-use crate::services::{HolidayService, WorkingHoursService};
+use crate::services::notification_service::generate_patient_cancellation_email;
+use crate::services::overbooking_service::find_policy;
+use crate::services::resource_service::{
+    appointment_resource_ids, bookable_resources, resource_bookings, set_appointment_resources,
+};
+use crate::services::{HolidayService, SettingsService, WorkingHoursService};
+use crate::utils::concurrency::{is_stale, version_conflict};
+use crate::utils::encryption::EncryptionKey;
+use crate::utils::AppError;
 use anyhow::{anyhow, Context, Result};
-use chrono::{DateTime, Duration, NaiveTime, TimeZone, Utc};
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, TimeZone, Utc};
 use chrono_tz::Europe::Rome;
-use sqlx::{PgPool, Postgres, Transaction};
+use sqlx::{PgConnection, PgPool};
 use std::collections::HashMap;
+use tokio::time::sleep;
+use tracing::{error, info, warn};
 use uuid::Uuid;
 use validator::Validate;
 
@@ -28,58 +49,93 @@ const FALLBACK_START_HOUR: u32 = 8; // 8:00 AM
 const FALLBACK_END_HOUR: u32 = 18; // 6:00 PM
 const DEFAULT_SLOT_DURATION: i64 = 30; // 30 minutes
 
+/// Free times suggested when a requested time is refused
+const MAX_ALTERNATIVE_SLOTS: usize = 3;
+
+/// Days before and after a refused time searched for alternatives
+const ALTERNATIVE_SEARCH_DAYS: i64 = 7;
+
+/// Pause between backfill runs once no appointment is left to encrypt
+const BACKFILL_INTERVAL_SECS: u64 = 300;
+
+/// Appointments encrypted per backfill run
+const BACKFILL_BATCH_SIZE: i64 = 200;
+
+/// Audit log value standing for free text, which is not logged
+const AUDIT_REDACTED_TEXT: &str = "[encrypted]";
+
+/// Cancellation reason of appointments cancelled from a reminder link
+const PATIENT_LINK_CANCELLATION_REASON: &str = "Cancelled by the patient from the reminder email";
+
+/// Spawn the background job encrypting the free text of appointments saved
+/// before it was encrypted
+pub fn spawn_appointment_text_encryption_backfill(pool: PgPool, encryption_key: EncryptionKey) {
+    let service = AppointmentService::new(pool, encryption_key);
+
+    tokio::spawn(async move {
+        loop {
+            match service.backfill_text_encryption().await {
+                Ok(encrypted) if encrypted as i64 == BACKFILL_BATCH_SIZE => continue,
+                Ok(0) => {}
+                Ok(encrypted) => info!("Encrypted the free text of {} appointments", encrypted),
+                Err(e) => error!("Appointment text encryption backfill failed: {:#}", e),
+            }
+            sleep(std::time::Duration::from_secs(BACKFILL_INTERVAL_SECS)).await;
+        }
+    });
+
+    info!("Appointment text encryption backfill spawned as background task");
+}
+
 /// Appointment service
 pub struct AppointmentService {
     pool: PgPool,
+    encryption_key: EncryptionKey,
 }
 
 impl AppointmentService {
     /// Create a new appointment service
-    pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+    pub fn new(pool: PgPool, encryption_key: EncryptionKey) -> Self {
+        Self {
+            pool,
+            encryption_key,
+        }
     }
 
-    /// Helper to set RLS context in a transaction
+    /// Create a new appointment with conflict detection
     ///
-    /// This sets the PostgreSQL session variables required by Row-Level Security policies.
-    async fn set_rls_context(
-        tx: &mut Transaction<'_, Postgres>,
-        user_id: Uuid,
-    ) -> Result<()> {
-        // Query the user's role from the database
-        let role: String = sqlx::query_scalar(
-            "SELECT role::TEXT FROM users WHERE id = $1"
-        )
-        .bind(user_id)
-        .fetch_one(&mut **tx)
-        .await
-        .context("Failed to fetch user role for RLS context")?;
+    /// This validates the appointment, checks for conflicts, and creates the record.
+    pub async fn create_appointment(
+        &self,
+        data: CreateAppointmentRequest,
+        created_by_id: Uuid,
+        request_ctx: Option<&RequestContext>,
+    ) -> Result<AppointmentDto> {
+        let mut tx = begin_with_rls(&self.pool, created_by_id).await?;
 
-        // Set RLS context variables using set_config() for parameterized queries
-        sqlx::query("SELECT set_config('app.current_user_id', $1, true)")
-            .bind(user_id.to_string())
-            .execute(&mut **tx)
-            .await
-            .context("Failed to set RLS user context")?;
+        let (appointment, audit_changes) = self
+            .create_appointment_in_tx(&mut tx, data, created_by_id)
+            .await?;
 
-        sqlx::query("SELECT set_config('app.current_user_role', $1, true)")
-            .bind(&role)
-            .execute(&mut **tx)
-            .await
-            .context("Failed to set RLS role context")?;
+        tx.commit().await?;
 
-        Ok(())
+        // Audit log (after commit, failures don't affect transaction)
+        self.log_change(AuditAction::Create, appointment.id, audit_changes, created_by_id, request_ctx)
+            .await;
+
+        appointment.decrypt(&self.encryption_key)
     }
 
-    /// Create a new appointment with conflict detection
+    /// Create an appointment in the caller's transaction (RLS context set)
     ///
-    /// This validates the appointment, checks for conflicts, and creates the record.
-    pub async fn create_appointment(
+    /// Returns the appointment and the changes to audit once the transaction
+    /// is committed.
+    pub(crate) async fn create_appointment_in_tx(
         &self,
+        tx: &mut PgConnection,
         data: CreateAppointmentRequest,
         created_by_id: Uuid,
-        request_ctx: Option<&RequestContext>,
-    ) -> Result<AppointmentDto> {
+    ) -> Result<(Appointment, serde_json::Value)> {
         // Validate request
         data.validate()
             .context("Invalid appointment data")?;
@@ -96,31 +152,25 @@ impl AppointmentService {
         let scheduled_end = data.scheduled_start
             + Duration::minutes(data.duration_minutes as i64);
 
-        // Validate against working hours and holidays BEFORE starting transaction
-        self.validate_working_hours_and_holidays(data.scheduled_start, scheduled_end)
+        // Check holidays, working hours and the provider's other appointments
+        let overbooked = self
+            .ensure_schedulable(
+                tx,
+                provider_id,
+                data.appointment_type,
+                &data.resource_ids,
+                data.scheduled_start,
+                scheduled_end,
+                None, // No existing appointment ID for creates
+                data.overbook,
+            )
             .await?;
 
-        // Start transaction for conflict check + insert
-        let mut tx = self.pool.begin().await?;
-
-        // Set RLS context
-        Self::set_rls_context(&mut tx, created_by_id).await?;
-
-        // Check for conflicts
-        self.check_conflicts(
-            &mut tx,
-            provider_id,
-            data.scheduled_start,
-            scheduled_end,
-            None, // No existing appointment ID for creates
-        )
-        .await?;
-
         // Verify patient exists
-        self.verify_patient_exists(&mut tx, patient_id).await?;
+        self.verify_patient_exists(tx, patient_id).await?;
 
         // Verify provider exists
-        self.verify_provider_exists(&mut tx, provider_id).await?;
+        self.verify_provider_exists(tx, provider_id).await?;
 
         // Insert appointment
         let appointment = sqlx::query_as::<_, Appointment>(
@@ -129,9 +179,9 @@ impl AppointmentService {
                 patient_id, provider_id, scheduled_start, scheduled_end,
                 duration_minutes, type, reason, notes,
                 is_recurring, recurring_pattern,
-                created_by, updated_by
+                created_by, updated_by, text_encrypted
             )
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, true)
             RETURNING *
             "#,
         )
@@ -141,8 +191,8 @@ impl AppointmentService {
         .bind(scheduled_end)
         .bind(data.duration_minutes)
         .bind(data.appointment_type)
-        .bind(data.reason)
-        .bind(data.notes)
+        .bind(self.encryption_key.encrypt_optional(&data.reason)?)
+        .bind(self.encryption_key.encrypt_optional(&data.notes)?)
         .bind(data.is_recurring.unwrap_or(false))
         .bind(data.recurring_pattern.map(sqlx::types::Json))
         .bind(created_by_id)
@@ -151,43 +201,42 @@ impl AppointmentService {
         .await
         .context("Failed to create appointment")?;
 
+        if !data.resource_ids.is_empty() {
+            set_appointment_resources(tx, appointment.id, &data.resource_ids).await?;
+        }
+
         // Create recurring appointments if needed
         if appointment.is_recurring {
             if let Some(pattern) = &appointment.recurring_pattern {
                 self.create_recurring_series(
-                    &mut tx,
+                    tx,
                     &appointment,
                     &pattern.0,
+                    &data.resource_ids,
                     created_by_id,
                 )
                 .await?;
             }
         }
 
-        tx.commit().await?;
-
-        // Audit log (after commit, failures don't affect transaction)
-        let _ = AuditLog::create(
-            &self.pool,
-            CreateAuditLog {
-                user_id: Some(created_by_id),
-                action: AuditAction::Create,
-                entity_type: EntityType::Appointment,
-                entity_id: Some(appointment.id.to_string()),
-                changes: Some(serde_json::json!({
-                    "patient_id": patient_id,
-                    "provider_id": provider_id,
-                    "scheduled_start": data.scheduled_start,
-                    "type": data.appointment_type,
-                })),
-                ip_address: request_ctx.and_then(|c| c.ip_address.clone()),
-                user_agent: request_ctx.and_then(|c| c.user_agent.clone()),
-                request_id: request_ctx.map(|c| c.request_id),
-            },
-        )
-        .await;
+        let mut audit_changes = serde_json::json!({
+            "patient_id": patient_id,
+            "provider_id": provider_id,
+            "scheduled_start": data.scheduled_start,
+            "type": data.appointment_type,
+        });
+        if !data.resource_ids.is_empty() {
+            audit_changes["resource_ids"] = serde_json::json!(data.resource_ids);
+        }
+        if !overbooked.is_empty() {
+            info!(
+                "Appointment {} overbooked over {:?} by {}",
+                appointment.id, overbooked, created_by_id
+            );
+            audit_changes["overbooked_appointment_ids"] = serde_json::json!(overbooked);
+        }
 
-        Ok(appointment.into())
+        Ok((appointment, audit_changes))
     }
 
     /// Get appointment by ID
@@ -199,8 +248,7 @@ impl AppointmentService {
     ) -> Result<Option<AppointmentDto>> {
         // If user_id is provided, use RLS context
         let appointment = if let Some(uid) = user_id {
-            let mut tx = self.pool.begin().await?;
-            Self::set_rls_context(&mut tx, uid).await?;
+            let mut tx = begin_with_rls(&self.pool, uid).await?;
 
             let appt = sqlx::query_as::<_, Appointment>(
                 r#"SELECT * FROM appointments WHERE id = $1"#,
@@ -238,7 +286,9 @@ impl AppointmentService {
             .await;
         }
 
-        Ok(appointment.map(|a| a.into()))
+        appointment
+            .map(|a| a.decrypt(&self.encryption_key))
+            .transpose()
     }
 
     /// Update appointment
@@ -249,17 +299,44 @@ impl AppointmentService {
         updated_by_id: Uuid,
         request_ctx: Option<&RequestContext>,
     ) -> Result<AppointmentDto> {
+        let mut tx = begin_with_rls(&self.pool, updated_by_id).await?;
+
+        let (updated, audit_changes) = self
+            .update_appointment_in_tx(&mut tx, id, data, updated_by_id)
+            .await?;
+
+        tx.commit().await?;
+
+        // Audit log (after commit)
+        if let Some(changes) = audit_changes {
+            self.log_change(AuditAction::Update, id, changes, updated_by_id, request_ctx)
+                .await;
+        }
+
+        updated.decrypt(&self.encryption_key)
+    }
+
+    /// Update an appointment in the caller's transaction (RLS context set)
+    ///
+    /// Returns the appointment and the changes to audit once the transaction
+    /// is committed (`None` when the request changes nothing).
+    pub(crate) async fn update_appointment_in_tx(
+        &self,
+        tx: &mut PgConnection,
+        id: Uuid,
+        data: UpdateAppointmentRequest,
+        updated_by_id: Uuid,
+    ) -> Result<(Appointment, Option<serde_json::Value>)> {
         // Validate request
         data.validate()
             .context("Invalid update data")?;
 
-        let mut tx = self.pool.begin().await?;
-
-        // Set RLS context
-        Self::set_rls_context(&mut tx, updated_by_id).await?;
-
         // Get existing appointment
-        let existing = self.get_appointment_for_update(&mut tx, id).await?;
+        let existing = self.get_appointment_for_update(tx, id).await?;
+
+        if is_stale(data.expected_version, existing.updated_at) {
+            return Err(version_conflict(&existing.decrypt(&self.encryption_key)?).into());
+        }
 
         // Check if status transition is valid
         if let Some(new_status) = data.status {
@@ -272,28 +349,41 @@ impl AppointmentService {
             }
         }
 
-        // If rescheduling, validate working hours/holidays and check for conflicts
-        if let Some(new_start) = data.scheduled_start {
-            let duration = data.duration_minutes.unwrap_or(existing.duration_minutes);
-            let new_end = new_start + Duration::minutes(duration as i64);
-
-            // Validate against working hours and holidays
-            self.validate_working_hours_and_holidays(new_start, new_end)
+        // If rescheduling, or changing the duration or the type (whose buffers
+        // may differ), validate working hours/holidays and check for conflicts
+        let appointment_type = data.appointment_type.unwrap_or(existing.appointment_type);
+        let resource_ids = match &data.resource_ids {
+            Some(resource_ids) => resource_ids.clone(),
+            None => appointment_resource_ids(tx, id).await?,
+        };
+        let new_start = data.scheduled_start.unwrap_or(existing.scheduled_start);
+        let duration = data.duration_minutes.unwrap_or(existing.duration_minutes);
+        let new_end = new_start + Duration::minutes(duration as i64);
+        let mut overbooked = Vec::new();
+        if data.scheduled_start.is_some()
+            || data.duration_minutes.is_some()
+            || appointment_type != existing.appointment_type
+        {
+            overbooked = self
+                .ensure_schedulable(
+                    tx,
+                    existing.provider_id,
+                    appointment_type,
+                    &resource_ids,
+                    new_start,
+                    new_end,
+                    Some(id), // Exclude current appointment from conflict check
+                    data.overbook,
+                )
                 .await?;
-
-            self.check_conflicts(
-                &mut tx,
-                existing.provider_id,
-                new_start,
-                new_end,
-                Some(id), // Exclude current appointment from conflict check
-            )
-            .await?;
-        } else if let Some(new_duration) = data.duration_minutes {
-            // If only duration is changing, need to validate the new end time
-            let new_end = existing.scheduled_start + Duration::minutes(new_duration as i64);
-            self.validate_working_hours_and_holidays(existing.scheduled_start, new_end)
+        } else if data.resource_ids.is_some() {
+            // Same time, other resources: only their calendars can conflict
+            let conflicts = self
+                .resource_conflicts(tx, &resource_ids, new_start, new_end, Some(id))
                 .await?;
+            if !conflicts.is_empty() {
+                return Err(scheduling_conflict(&conflicts, &[], false).into());
+            }
         }
 
         // Build update query dynamically
@@ -331,8 +421,12 @@ impl AppointmentService {
             param_index += 1;
         }
 
-        if updates.is_empty() {
-            return Ok(existing.into());
+        if updates.is_empty() && data.resource_ids.is_none() {
+            return Ok((existing, None));
+        }
+
+        if !existing.text_encrypted {
+            self.encrypt_legacy_text(tx, &existing).await?;
         }
 
         updates.push(format!("updated_by = ${}", param_index));
@@ -346,7 +440,15 @@ impl AppointmentService {
         );
 
         // Serialize data for audit log before consuming it
-        let audit_changes = serde_json::to_value(&data)?;
+        let mut audit_changes = serde_json::to_value(&data)?;
+        redact_free_text(&mut audit_changes);
+        if !overbooked.is_empty() {
+            info!(
+                "Appointment {} overbooked over {:?} by {}",
+                id, overbooked, updated_by_id
+            );
+            audit_changes["overbooked_appointment_ids"] = serde_json::json!(overbooked);
+        }
 
         let mut query = sqlx::query_as::<_, Appointment>(&query_str);
 
@@ -361,16 +463,16 @@ impl AppointmentService {
             query = query.bind(apt_type);
         }
         if let Some(reason) = data.reason {
-            query = query.bind(reason);
+            query = query.bind(self.encryption_key.encrypt(&reason)?);
         }
         if let Some(notes) = data.notes {
-            query = query.bind(notes);
+            query = query.bind(self.encryption_key.encrypt(&notes)?);
         }
         if let Some(status) = data.status {
             query = query.bind(status);
         }
         if let Some(cancel_reason) = data.cancellation_reason {
-            query = query.bind(cancel_reason);
+            query = query.bind(self.encryption_key.encrypt(&cancel_reason)?);
         }
         query = query.bind(updated_by_id);
         query = query.bind(id);
@@ -380,25 +482,11 @@ impl AppointmentService {
             .await
             .context("Failed to update appointment")?;
 
-        tx.commit().await?;
-
-        // Audit log (after commit)
-        let _ = AuditLog::create(
-            &self.pool,
-            CreateAuditLog {
-                user_id: Some(updated_by_id),
-                action: AuditAction::Update,
-                entity_type: EntityType::Appointment,
-                entity_id: Some(id.to_string()),
-                changes: Some(audit_changes),
-                ip_address: request_ctx.and_then(|c| c.ip_address.clone()),
-                user_agent: request_ctx.and_then(|c| c.user_agent.clone()),
-                request_id: request_ctx.map(|c| c.request_id),
-            },
-        )
-        .await;
+        if let Some(resource_ids) = &data.resource_ids {
+            set_appointment_resources(tx, id, resource_ids).await?;
+        }
 
-        Ok(updated.into())
+        Ok((updated, Some(audit_changes)))
     }
 
     /// Cancel appointment
@@ -409,14 +497,291 @@ impl AppointmentService {
         cancelled_by_id: Uuid,
         request_ctx: Option<&RequestContext>,
     ) -> Result<AppointmentDto> {
-        let mut tx = self.pool.begin().await?;
+        let mut tx = begin_with_rls(&self.pool, cancelled_by_id).await?;
 
-        // Set RLS context
-        Self::set_rls_context(&mut tx, cancelled_by_id).await?;
+        let (cancelled, audit_changes) = self
+            .cancel_appointment_in_tx(&mut tx, id, cancellation_reason, cancelled_by_id)
+            .await?;
+
+        tx.commit().await?;
+
+        // Audit log (after commit)
+        self.log_change(AuditAction::Update, id, audit_changes, cancelled_by_id, request_ctx)
+            .await;
+
+        cancelled.decrypt(&self.encryption_key)
+    }
+
+    /// Check a patient in: the appointment becomes ARRIVED, with the arrival
+    /// time (now when not given) as `checked_in_at`
+    ///
+    /// Fails with `AppError::BadRequest` for an arrival time in the future or
+    /// on another day than the appointment, and `AppError::Conflict` when the
+    /// patient is already checked in or the status does not allow it.
+    pub async fn check_in(
+        &self,
+        id: Uuid,
+        arrived_at: Option<DateTime<Utc>>,
+        checked_in_by_id: Uuid,
+        request_ctx: Option<&RequestContext>,
+    ) -> Result<AppointmentDto> {
+        let now = Utc::now();
+        let mut tx = begin_with_rls(&self.pool, checked_in_by_id).await?;
 
-        // Get existing appointment
         let existing = self.get_appointment_for_update(&mut tx, id).await?;
 
+        if existing.status == AppointmentStatus::Arrived {
+            return Err(AppError::Conflict("Patient is already checked in".to_string()).into());
+        }
+        if !existing.status.can_transition_to(&AppointmentStatus::Arrived) {
+            return Err(AppError::Conflict(format!(
+                "Appointment with status {:?} cannot be checked in",
+                existing.status
+            ))
+            .into());
+        }
+
+        let arrived_at = arrived_at.unwrap_or(now);
+        validate_arrival_time(arrived_at, existing.scheduled_start, now)
+            .map_err(|message| AppError::BadRequest(message.to_string()))?;
+
+        let arrived = sqlx::query_as::<_, Appointment>(
+            r#"
+            UPDATE appointments
+            SET status = 'ARRIVED',
+                checked_in_at = $1,
+                updated_at = NOW(),
+                updated_by = $2
+            WHERE id = $3
+            RETURNING *
+            "#,
+        )
+        .bind(arrived_at)
+        .bind(checked_in_by_id)
+        .bind(id)
+        .fetch_one(&mut *tx)
+        .await
+        .context("Failed to check in appointment")?;
+
+        tx.commit().await?;
+
+        // Audit log (after commit)
+        self.log_change(
+            AuditAction::Update,
+            id,
+            serde_json::json!({
+                "status": "ARRIVED",
+                "checked_in_at": arrived_at,
+            }),
+            checked_in_by_id,
+            request_ctx,
+        )
+        .await;
+
+        arrived.decrypt(&self.encryption_key)
+    }
+
+    /// Confirm or cancel an appointment from a reminder link, as the patient
+    ///
+    /// Runs with the patient's portal RLS context, so only their own
+    /// appointment is found. Using a link again changes nothing. Cancelling
+    /// withdraws the appointment's pending notifications and emails the
+    /// provider. Fails with "not found" for another patient's appointment and
+    /// "cannot be" when the status does not allow the action.
+    pub async fn respond_to_link(
+        &self,
+        claims: &AppointmentLinkClaims,
+    ) -> Result<AppointmentLinkOutcome> {
+        let mut tx = begin_portal_transaction(&self.pool, claims.patient_id).await?;
+
+        let existing = sqlx::query_as::<_, Appointment>(
+            "SELECT * FROM appointments WHERE id = $1 AND patient_id = $2 FOR UPDATE",
+        )
+        .bind(claims.appointment_id)
+        .bind(claims.patient_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| anyhow!("Appointment not found"))?;
+
+        let (provider_email, provider_first_name, provider_last_name): (String, String, String) =
+            sqlx::query_as("SELECT email, first_name, last_name FROM users WHERE id = $1")
+                .bind(existing.provider_id)
+                .fetch_one(&mut *tx)
+                .await
+                .context("Failed to load provider")?;
+        let provider_name = format!("Dr. {} {}", provider_first_name, provider_last_name);
+
+        let updated = match claims.action {
+            AppointmentLinkAction::Confirm => match existing.status {
+                AppointmentStatus::Confirmed | AppointmentStatus::Arrived => None,
+                AppointmentStatus::Scheduled => Some(
+                    sqlx::query_as::<_, Appointment>(
+                        r#"
+                        UPDATE appointments
+                        SET status = 'CONFIRMED', confirmed_at = NOW(), updated_at = NOW()
+                        WHERE id = $1
+                        RETURNING *
+                        "#,
+                    )
+                    .bind(existing.id)
+                    .fetch_one(&mut *tx)
+                    .await
+                    .context("Failed to confirm appointment")?,
+                ),
+                status => {
+                    return Err(anyhow!(
+                        "Appointment with status {:?} cannot be confirmed",
+                        status
+                    ))
+                }
+            },
+            AppointmentLinkAction::Cancel => {
+                if existing.status == AppointmentStatus::Cancelled {
+                    None
+                } else if !existing.can_cancel() {
+                    return Err(anyhow!(
+                        "Appointment with status {:?} cannot be cancelled",
+                        existing.status
+                    ));
+                } else {
+                    // Legacy rows keep no reason rather than mix plaintext
+                    // and ciphertext (see backfill_text_encryption)
+                    let reason = if existing.text_encrypted {
+                        Some(self.encryption_key.encrypt(PATIENT_LINK_CANCELLATION_REASON)?)
+                    } else {
+                        None
+                    };
+
+                    let cancelled = sqlx::query_as::<_, Appointment>(
+                        r#"
+                        UPDATE appointments
+                        SET status = 'CANCELLED',
+                            cancellation_reason = COALESCE($2, cancellation_reason),
+                            cancelled_at = NOW(),
+                            updated_at = NOW()
+                        WHERE id = $1
+                        RETURNING *
+                        "#,
+                    )
+                    .bind(existing.id)
+                    .bind(reason)
+                    .fetch_one(&mut *tx)
+                    .await
+                    .context("Failed to cancel appointment")?;
+
+                    sqlx::query(
+                        r#"
+                        UPDATE notification_queue
+                        SET status = 'CANCELLED'
+                        WHERE appointment_id = $1
+                          AND status IN ('PENDING', 'FAILED')
+                        "#,
+                    )
+                    .bind(existing.id)
+                    .execute(&mut *tx)
+                    .await
+                    .context("Failed to cancel appointment notifications")?;
+
+                    self.queue_patient_cancellation(
+                        &mut tx,
+                        &cancelled,
+                        &provider_email,
+                        &provider_name,
+                    )
+                    .await?;
+
+                    Some(cancelled)
+                }
+            }
+        };
+
+        tx.commit().await?;
+
+        let changed = updated.is_some();
+        let appointment = updated.unwrap_or(existing);
+
+        Ok(AppointmentLinkOutcome {
+            appointment_id: appointment.id,
+            status: appointment.status,
+            scheduled_start: appointment.scheduled_start,
+            scheduled_end: appointment.scheduled_end,
+            appointment_type: appointment.appointment_type,
+            provider_name,
+            changed,
+        })
+    }
+
+    /// Queue the email telling the provider that the patient cancelled
+    async fn queue_patient_cancellation(
+        &self,
+        tx: &mut PgConnection,
+        appointment: &Appointment,
+        provider_email: &str,
+        provider_name: &str,
+    ) -> Result<()> {
+        let (first_name, last_name): (String, String) =
+            sqlx::query_as("SELECT first_name, last_name FROM patients WHERE id = $1")
+                .bind(appointment.patient_id)
+                .fetch_one(&mut *tx)
+                .await
+                .context("Failed to load patient")?;
+        let patient_name = format!(
+            "{} {}",
+            self.encryption_key.decrypt(&first_name)?,
+            self.encryption_key.decrypt(&last_name)?
+        );
+
+        let appointment_type = serde_json::to_value(appointment.appointment_type)?
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+        let (subject, body) = generate_patient_cancellation_email(
+            provider_name,
+            &patient_name,
+            &appointment.scheduled_start,
+            &appointment_type,
+        );
+
+        sqlx::query(
+            r#"
+            INSERT INTO notification_queue (
+                id, patient_id, appointment_id, user_id, notification_type, delivery_method,
+                recipient_email, recipient_name, subject, message_body,
+                scheduled_for, priority, status
+            )
+            VALUES ($1, $2, $3, $4, $5, 'EMAIL', $6, $7, $8, $9, NOW(), 3, 'PENDING')
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(appointment.patient_id)
+        .bind(appointment.id)
+        .bind(appointment.provider_id)
+        .bind(NotificationType::PatientCancellation.as_str())
+        .bind(provider_email)
+        .bind(provider_name)
+        .bind(subject)
+        .bind(body)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to queue provider notification")?;
+
+        Ok(())
+    }
+
+    /// Cancel an appointment in the caller's transaction (RLS context set)
+    ///
+    /// Returns the appointment and the changes to audit once the transaction
+    /// is committed.
+    pub(crate) async fn cancel_appointment_in_tx(
+        &self,
+        tx: &mut PgConnection,
+        id: Uuid,
+        cancellation_reason: String,
+        cancelled_by_id: Uuid,
+    ) -> Result<(Appointment, serde_json::Value)> {
+        // Get existing appointment
+        let existing = self.get_appointment_for_update(tx, id).await?;
+
         // Check if can be cancelled
         if !existing.can_cancel() {
             return Err(anyhow!(
@@ -425,6 +790,10 @@ impl AppointmentService {
             ));
         }
 
+        if !existing.text_encrypted {
+            self.encrypt_legacy_text(tx, &existing).await?;
+        }
+
         // Update to cancelled
         let cancelled = sqlx::query_as::<_, Appointment>(
             r#"
@@ -439,35 +808,49 @@ impl AppointmentService {
             RETURNING *
             "#,
         )
-        .bind(&cancellation_reason)
+        .bind(self.encryption_key.encrypt(&cancellation_reason)?)
         .bind(cancelled_by_id)
         .bind(id)
         .fetch_one(&mut *tx)
         .await
         .context("Failed to cancel appointment")?;
 
-        tx.commit().await?;
+        let audit_changes = serde_json::json!({
+            "status": "CANCELLED",
+            "cancellation_reason": AUDIT_REDACTED_TEXT,
+        });
 
-        // Audit log (after commit)
+        Ok((cancelled, audit_changes))
+    }
+
+    /// Write the audit log of a committed change to an appointment
+    pub(crate) async fn log_change(
+        &self,
+        action: AuditAction,
+        id: Uuid,
+        changes: serde_json::Value,
+        user_id: Uuid,
+        request_ctx: Option<&RequestContext>,
+    ) {
         let _ = AuditLog::create(
             &self.pool,
             CreateAuditLog {
-                user_id: Some(cancelled_by_id),
-                action: AuditAction::Update,
+                user_id: Some(user_id),
+                action,
                 entity_type: EntityType::Appointment,
                 entity_id: Some(id.to_string()),
-                changes: Some(serde_json::json!({
-                    "status": "CANCELLED",
-                    "cancellation_reason": cancellation_reason,
-                })),
+                changes: Some(changes),
                 ip_address: request_ctx.and_then(|c| c.ip_address.clone()),
                 user_agent: request_ctx.and_then(|c| c.user_agent.clone()),
                 request_id: request_ctx.map(|c| c.request_id),
             },
         )
         .await;
+    }
 
-        Ok(cancelled.into())
+    /// Decrypt an appointment read in a transaction
+    pub(crate) fn decrypt(&self, appointment: Appointment) -> Result<AppointmentDto> {
+        appointment.decrypt(&self.encryption_key)
     }
 
     /// Delete appointment (soft delete by cancelling)
@@ -540,8 +923,7 @@ impl AppointmentService {
 
         // Use transaction with RLS context if user_id is provided
         let (total, appointments) = if let Some(uid) = user_id {
-            let mut tx = self.pool.begin().await?;
-            Self::set_rls_context(&mut tx, uid).await?;
+            let mut tx = begin_with_rls(&self.pool, uid).await?;
 
             // Count query
             let count_query = format!("SELECT COUNT(*) FROM appointments {}", where_clause);
@@ -679,11 +1061,50 @@ impl AppointmentService {
             .await;
         }
 
-        let dtos = appointments.into_iter().map(|a| a.into()).collect();
+        let dtos = appointments
+            .into_iter()
+            .map(|a| a.decrypt(&self.encryption_key))
+            .collect::<Result<Vec<_>>>()?;
 
         Ok((dtos, total))
     }
 
+    /// Upcoming appointments of a patient, soonest first
+    ///
+    /// Pending, scheduled and confirmed appointments that have not ended yet,
+    /// for the patient chart.
+    pub async fn get_upcoming_for_patient(
+        &self,
+        patient_id: Uuid,
+        limit: i64,
+        user_id: Uuid,
+    ) -> Result<Vec<AppointmentDto>> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        let appointments = sqlx::query_as::<_, Appointment>(
+            r#"
+            SELECT * FROM appointments
+            WHERE patient_id = $1
+              AND scheduled_end >= NOW()
+              AND status IN ('PENDING', 'SCHEDULED', 'CONFIRMED')
+            ORDER BY scheduled_start
+            LIMIT $2
+            "#,
+        )
+        .bind(patient_id)
+        .bind(limit.clamp(1, 100))
+        .fetch_all(&mut *tx)
+        .await
+        .context("Failed to fetch upcoming appointments")?;
+
+        tx.commit().await?;
+
+        appointments
+            .into_iter()
+            .map(|a| a.decrypt(&self.encryption_key))
+            .collect()
+    }
+
     /// Get appointment statistics
     pub async fn get_statistics(&self) -> Result<AppointmentStatistics> {
         // Total appointments
@@ -765,6 +1186,22 @@ impl AppointmentService {
             0.0
         };
 
+        // Wait from arrival to the start of the visit, and visit duration
+        // (start to completion), of the appointments that have the times
+        let (avg_wait_minutes, avg_visit_duration_minutes): (f64, f64) = sqlx::query_as(
+            r#"
+            SELECT
+                COALESCE(AVG(EXTRACT(EPOCH FROM (visit_started_at - checked_in_at)) / 60)
+                    FILTER (WHERE checked_in_at IS NOT NULL AND visit_started_at >= checked_in_at), 0)::FLOAT,
+                COALESCE(AVG(EXTRACT(EPOCH FROM (checked_out_at - visit_started_at)) / 60)
+                    FILTER (WHERE status = 'COMPLETED' AND checked_out_at >= visit_started_at), 0)::FLOAT
+            FROM appointments
+            WHERE visit_started_at IS NOT NULL
+            "#,
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
         Ok(AppointmentStatistics {
             total,
             by_status,
@@ -773,22 +1210,171 @@ impl AppointmentService {
             upcoming_week,
             no_show_rate,
             cancellation_rate,
+            avg_wait_minutes,
+            avg_visit_duration_minutes,
         })
     }
 
-    /// Validate appointment time against working hours and holidays
+    /// Buffer minutes per appointment type
     ///
-    /// Checks that:
-    /// 1. The date is not a holiday
-    /// 2. The date is a working day
-    /// 3. The time falls within working hours
-    /// 4. The time does not overlap with break times
-    async fn validate_working_hours_and_holidays(
+    /// None when the setting is missing; an invalid value is logged and
+    /// ignored rather than blocking scheduling.
+    async fn buffers(&self) -> AppointmentBuffers {
+        let setting = SettingsService::new(self.pool.clone())
+            .get_setting(APPOINTMENT_BUFFERS_SETTING)
+            .await;
+
+        match setting {
+            Ok(Some(setting)) => parse_appointment_buffers(&setting.setting_value)
+                .unwrap_or_else(|e| {
+                    warn!("Ignoring invalid {}: {}", APPOINTMENT_BUFFERS_SETTING, e);
+                    AppointmentBuffers::default()
+                }),
+            Ok(None) => AppointmentBuffers::default(),
+            Err(e) => {
+                warn!("Failed to load {}: {}", APPOINTMENT_BUFFERS_SETTING, e);
+                AppointmentBuffers::default()
+            }
+        }
+    }
+
+    /// Refuse an appointment time that cannot be booked
+    ///
+    /// Fails with `AppError::SchedulingConflict` listing every reason (a
+    /// holiday, a non-working day, outside working hours, the break,
+    /// appointments of the provider overlapping it or its buffers, or
+    /// resources reserved by other appointments) and the nearest free times
+    /// of the same length, instead of booking the time twice.
+    ///
+    /// With `overbook`, a time taken only by other appointments of the
+    /// provider is accepted when their overbooking policy allows it; the
+    /// caller checks the `overbook` permission. Resources are never
+    /// overbooked. Returns the appointments overbooked.
+    #[allow(clippy::too_many_arguments)]
+    async fn ensure_schedulable(
         &self,
+        tx: &mut PgConnection,
+        provider_id: Uuid,
+        appointment_type: AppointmentType,
+        resource_ids: &[Uuid],
         scheduled_start: DateTime<Utc>,
         scheduled_end: DateTime<Utc>,
-    ) -> Result<()> {
-        let date_naive = scheduled_start.date_naive();
+        exclude_id: Option<Uuid>,
+        overbook: bool,
+    ) -> Result<Vec<Uuid>> {
+        let buffers = self.buffers().await;
+
+        let mut conflicts: Vec<SchedulingConflict> = self
+            .calendar_conflict(scheduled_start, scheduled_end)
+            .await?
+            .into_iter()
+            .collect();
+        conflicts.extend(
+            self.overlapping_appointments(
+                tx,
+                &buffers,
+                provider_id,
+                appointment_type,
+                scheduled_start,
+                scheduled_end,
+                exclude_id,
+            )
+            .await?,
+        );
+        conflicts.extend(
+            self.resource_conflicts(tx, resource_ids, scheduled_start, scheduled_end, exclude_id)
+                .await?,
+        );
+
+        if conflicts.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // Other appointments can be overbooked, closed hours cannot
+        let overbookable = if conflicts.iter().all(|conflict| {
+            matches!(
+                conflict.kind,
+                SchedulingConflictKind::Overlap | SchedulingConflictKind::BufferTime
+            )
+        }) {
+            find_policy(tx, provider_id).await?.is_some_and(|policy| {
+                policy.allows(
+                    appointment_type,
+                    scheduled_start.with_timezone(&Rome).time(),
+                    scheduled_end.with_timezone(&Rome).time(),
+                    conflicts.len(),
+                )
+            })
+        } else {
+            false
+        };
+
+        if overbook && overbookable {
+            return Ok(conflicts.iter().filter_map(|c| c.appointment_id).collect());
+        }
+
+        let alternatives = self
+            .alternative_slots(
+                tx,
+                &buffers,
+                provider_id,
+                appointment_type,
+                resource_ids,
+                scheduled_start,
+                scheduled_end - scheduled_start,
+                exclude_id,
+                Utc::now(),
+            )
+            .await?;
+
+        Err(scheduling_conflict(&conflicts, &alternatives, overbookable).into())
+    }
+
+    /// Resources reserved by other appointments at a time, as conflicts
+    ///
+    /// Fails when a resource does not exist or is inactive.
+    async fn resource_conflicts(
+        &self,
+        tx: &mut PgConnection,
+        resource_ids: &[Uuid],
+        scheduled_start: DateTime<Utc>,
+        scheduled_end: DateTime<Utc>,
+        exclude_id: Option<Uuid>,
+    ) -> Result<Vec<SchedulingConflict>> {
+        let names = bookable_resources(tx, resource_ids).await?;
+        let bookings =
+            resource_bookings(tx, resource_ids, scheduled_start, scheduled_end, exclude_id)
+                .await?;
+
+        Ok(bookings
+            .into_iter()
+            .map(|booking| SchedulingConflict {
+                kind: SchedulingConflictKind::ResourceBusy,
+                message: format!(
+                    "{} is reserved by another appointment at this time",
+                    names
+                        .get(&booking.resource_id)
+                        .map(String::as_str)
+                        .unwrap_or("A resource")
+                ),
+                start: Some(booking.scheduled_start),
+                end: Some(booking.scheduled_end),
+                appointment_id: Some(booking.appointment_id),
+                resource_id: Some(booking.resource_id),
+            })
+            .collect())
+    }
+
+    /// Check an appointment time against holidays and working hours
+    ///
+    /// Returns why the practice is closed at that time, if it is: a holiday,
+    /// a non-working day, outside working hours or during the break.
+    async fn calendar_conflict(
+        &self,
+        scheduled_start: DateTime<Utc>,
+        scheduled_end: DateTime<Utc>,
+    ) -> Result<Option<SchedulingConflict>> {
+        let date_naive = scheduled_start.with_timezone(&Rome).date_naive();
 
         // Check if this date is a holiday
         let holiday_service = HolidayService::new(self.pool.clone());
@@ -798,10 +1384,17 @@ impl AppointmentService {
             .map_err(|e| anyhow!("Failed to check holiday: {}", e))?;
 
         if is_holiday {
-            return Err(anyhow!(
-                "Cannot schedule appointment on a holiday ({})",
-                date_naive.format("%Y-%m-%d")
-            ));
+            return Ok(Some(SchedulingConflict {
+                kind: SchedulingConflictKind::Holiday,
+                message: format!(
+                    "Cannot schedule appointment on a holiday ({})",
+                    date_naive.format("%Y-%m-%d")
+                ),
+                start: None,
+                end: None,
+                appointment_id: None,
+                resource_id: None,
+            }));
         }
 
         // Get effective working hours for this date
@@ -811,140 +1404,188 @@ impl AppointmentService {
             .await
             .map_err(|e| anyhow!("Failed to get working hours: {}", e))?;
 
-        // Check if it's a working day
-        if !effective_hours.is_working_day {
-            return Err(anyhow!(
-                "Cannot schedule appointment on a non-working day ({})",
-                date_naive.format("%Y-%m-%d")
-            ));
-        }
+        Ok(working_hours_conflict(&effective_hours, scheduled_start, scheduled_end))
+    }
 
-        // Parse working hours
-        let start_time = effective_hours
-            .start_time
-            .as_ref()
-            .and_then(|t| parse_time_str(t))
-            .unwrap_or((FALLBACK_START_HOUR, 0));
-
-        let end_time = effective_hours
-            .end_time
-            .as_ref()
-            .and_then(|t| parse_time_str(t))
-            .unwrap_or((FALLBACK_END_HOUR, 0));
-
-        // Get appointment times in local timezone (Europe/Rome)
-        // Working hours are defined in local time, so we must convert UTC to local for comparison
-        let local_start = scheduled_start.with_timezone(&Rome);
-        let local_end = scheduled_end.with_timezone(&Rome);
-        let appt_start_time = local_start.time();
-        let appt_end_time = local_end.time();
-
-        let working_start = NaiveTime::from_hms_opt(start_time.0, start_time.1, 0)
-            .unwrap_or(NaiveTime::from_hms_opt(8, 0, 0).unwrap());
-        let working_end = NaiveTime::from_hms_opt(end_time.0, end_time.1, 0)
-            .unwrap_or(NaiveTime::from_hms_opt(18, 0, 0).unwrap());
-
-        // Check if appointment is within working hours
-        if appt_start_time < working_start || appt_end_time > working_end {
-            return Err(anyhow!(
-                "Appointment time ({} - {}) is outside working hours ({} - {})",
-                appt_start_time.format("%H:%M"),
-                appt_end_time.format("%H:%M"),
-                working_start.format("%H:%M"),
-                working_end.format("%H:%M")
-            ));
-        }
+    /// Appointments of the provider overlapping a time, as conflicts
+    ///
+    /// Appointments that only overlap the buffers of either appointment are
+    /// reported as BUFFER_TIME, with their time including their buffers.
+    #[allow(clippy::too_many_arguments)]
+    async fn overlapping_appointments(
+        &self,
+        tx: &mut PgConnection,
+        buffers: &AppointmentBuffers,
+        provider_id: Uuid,
+        appointment_type: AppointmentType,
+        scheduled_start: DateTime<Utc>,
+        scheduled_end: DateTime<Utc>,
+        exclude_id: Option<Uuid>,
+    ) -> Result<Vec<SchedulingConflict>> {
+        // Buffers on both sides can separate the appointments
+        let reach = Duration::minutes(2 * buffers.reach());
+        let nearby = sqlx::query_as::<_, (Uuid, AppointmentType, DateTime<Utc>, DateTime<Utc>)>(
+            r#"
+            SELECT id, type, scheduled_start, scheduled_end
+            FROM appointments
+            WHERE provider_id = $1
+              AND ($2::UUID IS NULL OR id != $2)
+              AND status NOT IN ('CANCELLED', 'NO_SHOW')
+              AND tstzrange(scheduled_start, scheduled_end) && tstzrange($3, $4)
+            ORDER BY scheduled_start
+            "#,
+        )
+        .bind(provider_id)
+        .bind(exclude_id)
+        .bind(scheduled_start - reach)
+        .bind(scheduled_end + reach)
+        .fetch_all(&mut *tx)
+        .await?;
 
-        // Check if appointment overlaps with break time
-        if let (Some(break_start_str), Some(break_end_str)) =
-            (&effective_hours.break_start, &effective_hours.break_end)
-        {
-            if let (Some((break_start_h, break_start_m)), Some((break_end_h, break_end_m))) =
-                (parse_time_str(break_start_str), parse_time_str(break_end_str))
-            {
-                let break_start = NaiveTime::from_hms_opt(break_start_h, break_start_m, 0)
-                    .unwrap_or(NaiveTime::from_hms_opt(12, 0, 0).unwrap());
-                let break_end = NaiveTime::from_hms_opt(break_end_h, break_end_m, 0)
-                    .unwrap_or(NaiveTime::from_hms_opt(13, 0, 0).unwrap());
-
-                // Check if appointment overlaps with break
-                // Overlap occurs if: appt_start < break_end AND appt_end > break_start
-                if appt_start_time < break_end && appt_end_time > break_start {
-                    return Err(anyhow!(
-                        "Appointment time ({} - {}) overlaps with break time ({} - {})",
-                        appt_start_time.format("%H:%M"),
-                        appt_end_time.format("%H:%M"),
-                        break_start.format("%H:%M"),
-                        break_end.format("%H:%M")
-                    ));
+        Ok(nearby
+            .into_iter()
+            .filter_map(|(id, booked_type, start, end)| {
+                if start < scheduled_end && end > scheduled_start {
+                    return Some(SchedulingConflict {
+                        kind: SchedulingConflictKind::Overlap,
+                        message: "Scheduling conflict detected for provider at this time"
+                            .to_string(),
+                        start: Some(start),
+                        end: Some(end),
+                        appointment_id: Some(id),
+                        resource_id: None,
+                    });
                 }
-            }
-        }
 
-        Ok(())
+                let (busy_start, busy_end) =
+                    buffers.busy(booked_type, start, end, Some(appointment_type));
+                if busy_start < scheduled_end && busy_end > scheduled_start {
+                    let (blocked_start, blocked_end) = buffers.blocked(booked_type, start, end);
+                    return Some(SchedulingConflict {
+                        kind: SchedulingConflictKind::BufferTime,
+                        message: "Not enough preparation or clean-up time between appointments"
+                            .to_string(),
+                        start: Some(blocked_start),
+                        end: Some(blocked_end),
+                        appointment_id: Some(id),
+                        resource_id: None,
+                    });
+                }
+
+                None
+            })
+            .collect())
     }
 
-    /// Check for scheduling conflicts
+    /// Free times of the provider nearest to a refused time
     ///
-    /// Returns an error if there's an overlapping appointment for the same provider
-    async fn check_conflicts(
+    /// Searches the working hours of the days up to
+    /// `ALTERNATIVE_SEARCH_DAYS` before and after it, skipping holidays and
+    /// the past, in the 30-minute steps of the availability check. Returns
+    /// at most `MAX_ALTERNATIVE_SLOTS` times of the same length, in time
+    /// order, that leave room for the buffers and during which the
+    /// resources are free.
+    #[allow(clippy::too_many_arguments)]
+    async fn alternative_slots(
         &self,
-        tx: &mut Transaction<'_, Postgres>,
+        tx: &mut PgConnection,
+        buffers: &AppointmentBuffers,
         provider_id: Uuid,
-        scheduled_start: DateTime<Utc>,
-        scheduled_end: DateTime<Utc>,
+        appointment_type: AppointmentType,
+        resource_ids: &[Uuid],
+        requested_start: DateTime<Utc>,
+        duration: Duration,
         exclude_id: Option<Uuid>,
-    ) -> Result<()> {
-        let query = if let Some(id) = exclude_id {
-            sqlx::query_scalar::<_, i64>(
-                r#"
-                SELECT COUNT(*)
-                FROM appointments
-                WHERE provider_id = $1
-                  AND id != $2
-                  AND status NOT IN ('CANCELLED', 'NO_SHOW')
-                  AND tstzrange(scheduled_start, scheduled_end) && tstzrange($3, $4)
-                "#,
-            )
-            .bind(provider_id)
-            .bind(id)
-            .bind(scheduled_start)
-            .bind(scheduled_end)
-        } else {
-            sqlx::query_scalar::<_, i64>(
+        now: DateTime<Utc>,
+    ) -> Result<Vec<AlternativeSlot>> {
+        let holiday_service = HolidayService::new(self.pool.clone());
+        let working_hours_service = WorkingHoursService::new(self.pool.clone());
+
+        let requested_date = requested_start.with_timezone(&Rome).date_naive();
+        let first_date = (requested_date - Duration::days(ALTERNATIVE_SEARCH_DAYS))
+            .max(now.with_timezone(&Rome).date_naive());
+        let last_date = requested_date + Duration::days(ALTERNATIVE_SEARCH_DAYS);
+
+        let mut candidates = Vec::new();
+        for date in first_date.iter_days().take_while(|date| *date <= last_date) {
+            let is_holiday = holiday_service
+                .is_holiday(date)
+                .await
+                .map_err(|e| anyhow!("Failed to check holiday: {}", e))?;
+            if is_holiday {
+                continue;
+            }
+
+            let effective_hours = working_hours_service
+                .get_effective_hours_for_date(date)
+                .await
+                .map_err(|e| anyhow!("Failed to get working hours: {}", e))?;
+            if !effective_hours.is_working_day {
+                continue;
+            }
+
+            let window = working_window(&effective_hours)?;
+            let reach = Duration::minutes(2 * buffers.reach());
+            let booked = sqlx::query_as::<_, (AppointmentType, DateTime<Utc>, DateTime<Utc>)>(
                 r#"
-                SELECT COUNT(*)
+                SELECT type, scheduled_start, scheduled_end
                 FROM appointments
                 WHERE provider_id = $1
+                  AND ($2::UUID IS NULL OR id != $2)
                   AND status NOT IN ('CANCELLED', 'NO_SHOW')
-                  AND tstzrange(scheduled_start, scheduled_end) && tstzrange($2, $3)
+                  AND scheduled_start < $4
+                  AND scheduled_end > $3
                 "#,
             )
             .bind(provider_id)
-            .bind(scheduled_start)
-            .bind(scheduled_end)
-        };
-
-        let conflicts = query.fetch_one(&mut **tx).await?;
+            .bind(exclude_id)
+            .bind(window.0 - reach)
+            .bind(window.1 + reach)
+            .fetch_all(&mut *tx)
+            .await?;
+            let mut busy: Vec<_> = booked
+                .into_iter()
+                .map(|(booked_type, start, end)| {
+                    buffers.busy(booked_type, start, end, Some(appointment_type))
+                })
+                .collect();
+            busy.extend(
+                resource_bookings(tx, resource_ids, window.0, window.1, exclude_id)
+                    .await?
+                    .into_iter()
+                    .map(|booking| (booking.scheduled_start, booking.scheduled_end)),
+            );
 
-        if conflicts > 0 {
-            return Err(anyhow!("Scheduling conflict detected for provider at this time"));
+            candidates.extend(
+                day_time_slots(&effective_hours, window, &busy, duration.num_minutes() as i32)
+                    .into_iter()
+                    .filter(|slot| {
+                        slot.available
+                            && slot.start > now
+                            && working_hours_conflict(&effective_hours, slot.start, slot.end)
+                                .is_none()
+                    })
+                    .map(|slot| AlternativeSlot {
+                        start: slot.start,
+                        end: slot.end,
+                    }),
+            );
         }
 
-        Ok(())
+        Ok(nearest_slots(candidates, requested_start, MAX_ALTERNATIVE_SLOTS))
     }
 
     /// Verify patient exists
     async fn verify_patient_exists(
         &self,
-        tx: &mut Transaction<'_, Postgres>,
+        tx: &mut PgConnection,
         patient_id: Uuid,
     ) -> Result<()> {
         let exists: bool = sqlx::query_scalar(
             "SELECT EXISTS(SELECT 1 FROM patients WHERE id = $1 AND status = 'ACTIVE')",
         )
         .bind(patient_id)
-        .fetch_one(&mut **tx)
+        .fetch_one(&mut *tx)
         .await?;
 
         if !exists {
@@ -957,14 +1598,14 @@ impl AppointmentService {
     /// Verify provider exists
     async fn verify_provider_exists(
         &self,
-        tx: &mut Transaction<'_, Postgres>,
+        tx: &mut PgConnection,
         provider_id: Uuid,
     ) -> Result<()> {
         let exists: bool = sqlx::query_scalar(
             "SELECT EXISTS(SELECT 1 FROM users WHERE id = $1 AND is_active = true)",
         )
         .bind(provider_id)
-        .fetch_one(&mut **tx)
+        .fetch_one(&mut *tx)
         .await?;
 
         if !exists {
@@ -974,29 +1615,87 @@ impl AppointmentService {
         Ok(())
     }
 
+    /// Encrypt the free text of appointments saved before it was encrypted
+    ///
+    /// Returns how many appointments were encrypted (at most one batch).
+    pub async fn backfill_text_encryption(&self) -> Result<usize> {
+        let mut tx = self.pool.begin().await?;
+        apply_rls_context(&mut tx, SYSTEM_USER_ID, SYSTEM_ROLE).await?;
+
+        let appointments = sqlx::query_as::<_, Appointment>(
+            r#"
+            SELECT * FROM appointments
+            WHERE NOT text_encrypted
+            ORDER BY created_at
+            LIMIT $1
+            "#,
+        )
+        .bind(BACKFILL_BATCH_SIZE)
+        .fetch_all(&mut *tx)
+        .await
+        .context("Failed to fetch appointments with plaintext text")?;
+
+        for appointment in &appointments {
+            self.encrypt_legacy_text(&mut tx, appointment).await?;
+        }
+
+        tx.commit().await?;
+        Ok(appointments.len())
+    }
+
+    /// Encrypt the plaintext reason, notes and cancellation reason of an
+    /// appointment saved before they were encrypted
+    async fn encrypt_legacy_text(
+        &self,
+        tx: &mut PgConnection,
+        appointment: &Appointment,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE appointments
+            SET reason = $2, notes = $3, cancellation_reason = $4, text_encrypted = true
+            WHERE id = $1 AND NOT text_encrypted
+            "#,
+        )
+        .bind(appointment.id)
+        .bind(self.encryption_key.encrypt_optional(&appointment.reason)?)
+        .bind(self.encryption_key.encrypt_optional(&appointment.notes)?)
+        .bind(
+            self.encryption_key
+                .encrypt_optional(&appointment.cancellation_reason)?,
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Failed to encrypt appointment text")?;
+
+        Ok(())
+    }
+
     /// Get appointment for update (with lock)
     async fn get_appointment_for_update(
         &self,
-        tx: &mut Transaction<'_, Postgres>,
+        tx: &mut PgConnection,
         id: Uuid,
     ) -> Result<Appointment> {
         sqlx::query_as::<_, Appointment>(
             "SELECT * FROM appointments WHERE id = $1 FOR UPDATE",
         )
         .bind(id)
-        .fetch_optional(&mut **tx)
+        .fetch_optional(&mut *tx)
         .await?
         .ok_or_else(|| anyhow!("Appointment not found"))
     }
 
     /// Create recurring appointment series
     ///
-    /// Automatically skips holidays, non-working days, and conflicting time slots.
+    /// Automatically skips holidays, non-working days, and conflicting time
+    /// slots, including those when one of the resources is reserved.
     async fn create_recurring_series(
         &self,
-        tx: &mut Transaction<'_, Postgres>,
+        tx: &mut PgConnection,
         parent: &Appointment,
         pattern: &RecurringPattern,
+        resource_ids: &[Uuid],
         created_by_id: Uuid,
     ) -> Result<()> {
         let mut current_date = parent.scheduled_start;
@@ -1004,6 +1703,7 @@ impl AppointmentService {
         let mut skipped = 0;
         let max_count = pattern.max_occurrences.unwrap_or(52); // Default to 1 year
         let max_attempts = max_count * 3; // Allow up to 3x attempts to find valid slots
+        let buffers = self.buffers().await;
 
         while count < max_count && skipped < max_attempts {
             // Calculate next occurrence
@@ -1019,11 +1719,10 @@ impl AppointmentService {
             let scheduled_end = current_date + Duration::minutes(parent.duration_minutes as i64);
 
             // Skip holidays and non-working days
-            if self
-                .validate_working_hours_and_holidays(current_date, scheduled_end)
-                .await
-                .is_err()
-            {
+            if !matches!(
+                self.calendar_conflict(current_date, scheduled_end).await,
+                Ok(None)
+            ) {
                 tracing::debug!(
                     date = %current_date.date_naive(),
                     "Skipping recurring appointment - holiday or non-working day"
@@ -1033,35 +1732,57 @@ impl AppointmentService {
             }
 
             // Check for conflicts with other appointments
-            if self.check_conflicts(
+            if !matches!(
+                self.overlapping_appointments(
+                    tx,
+                    &buffers,
+                    parent.provider_id,
+                    parent.appointment_type,
+                    current_date,
+                    scheduled_end,
+                    None,
+                )
+                .await,
+                Ok(ref overlaps) if overlaps.is_empty()
+            ) {
+                // Skip conflicting appointments
+                tracing::debug!(
+                    date = %current_date.date_naive(),
+                    "Skipping recurring appointment - conflict with existing appointment"
+                );
+                skipped += 1;
+                continue;
+            }
+
+            let resources_busy = !resource_bookings(
                 tx,
-                parent.provider_id,
+                resource_ids,
                 current_date,
                 scheduled_end,
                 None,
             )
-            .await
-            .is_err()
-            {
-                // Skip conflicting appointments
+            .await?
+            .is_empty();
+            if resources_busy {
                 tracing::debug!(
                     date = %current_date.date_naive(),
-                    "Skipping recurring appointment - conflict with existing appointment"
+                    "Skipping recurring appointment - resource reserved"
                 );
                 skipped += 1;
                 continue;
             }
 
             // Create appointment in series
-            sqlx::query(
+            let occurrence_id: Uuid = sqlx::query_scalar(
                 r#"
                 INSERT INTO appointments (
                     patient_id, provider_id, scheduled_start, scheduled_end,
                     duration_minutes, type, reason, notes,
                     is_recurring, parent_appointment_id,
-                    created_by, updated_by
+                    created_by, updated_by, text_encrypted
                 )
-                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, false, $9, $10, $11)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, false, $9, $10, $11, $12)
+                RETURNING id
                 "#,
             )
             .bind(parent.patient_id)
@@ -1075,9 +1796,14 @@ impl AppointmentService {
             .bind(parent.id) // parent_appointment_id
             .bind(created_by_id)
             .bind(created_by_id)
-            .execute(&mut **tx)
+            .bind(parent.text_encrypted)
+            .fetch_one(&mut *tx)
             .await?;
 
+            if !resource_ids.is_empty() {
+                set_appointment_resources(tx, occurrence_id, resource_ids).await?;
+            }
+
             count += 1;
         }
 
@@ -1112,13 +1838,21 @@ impl AppointmentService {
     /// Check appointment availability for a provider on a given date
     ///
     /// Returns time slots showing which are available and which are booked.
-    /// Respects working hours configuration, overrides, and holidays.
+    /// Respects working hours configuration, overrides, holidays and the
+    /// buffers of the booked appointments, and those of `appointment_type`
+    /// when given.
+    ///
+    /// Slots during which one of `resource_ids` is reserved are unavailable
+    /// and list the reserved resources; also returns the bottleneck, the
+    /// resource reserved in the most slots the provider is free in.
     pub async fn check_availability(
         &self,
         provider_id: Uuid,
         date: DateTime<Utc>,
         duration_minutes: i32,
-    ) -> Result<Vec<TimeSlot>> {
+        appointment_type: Option<AppointmentType>,
+        resource_ids: &[Uuid],
+    ) -> Result<(Vec<TimeSlot>, Option<Uuid>)> {
         let date_naive = date.date_naive();
 
         // Check if this date is a holiday (block all appointments on holidays)
@@ -1131,7 +1865,7 @@ impl AppointmentService {
         // If this is a holiday, return empty slots (no appointments on holidays)
         if is_holiday {
             tracing::debug!(date = %date_naive, "Date is a holiday, no availability");
-            return Ok(Vec::new());
+            return Ok((Vec::new(), None));
         }
 
         // Get effective working hours for this date
@@ -1143,101 +1877,117 @@ impl AppointmentService {
 
         // If not a working day, return empty slots
         if !effective_hours.is_working_day {
-            return Ok(Vec::new());
+            return Ok((Vec::new(), None));
         }
 
-        // Parse working hours (with fallback to defaults)
-        let (start_hour, start_min) = match &effective_hours.start_time {
-            Some(time) => parse_time_str(time).unwrap_or((FALLBACK_START_HOUR, 0)),
-            None => (FALLBACK_START_HOUR, 0),
-        };
+        let (day_start_utc, day_end_utc) = working_window(&effective_hours)?;
+        let buffers = self.buffers().await;
+        let reach = Duration::minutes(2 * buffers.reach());
 
-        let (end_hour, end_min) = match &effective_hours.end_time {
-            Some(time) => parse_time_str(time).unwrap_or((FALLBACK_END_HOUR, 0)),
-            None => (FALLBACK_END_HOUR, 0),
-        };
-
-        // Parse break times if configured
-        let break_start = effective_hours.break_start.as_ref().and_then(|t| parse_time_str(t));
-        let break_end = effective_hours.break_end.as_ref().and_then(|t| parse_time_str(t));
-
-        let day_start = date_naive.and_hms_opt(start_hour, start_min, 0)
-            .ok_or_else(|| anyhow!("Invalid start time"))?;
-        let day_end = date_naive.and_hms_opt(end_hour, end_min, 0)
-            .ok_or_else(|| anyhow!("Invalid end time"))?;
-
-        // Convert local times (Europe/Rome) to UTC for database queries
-        // Working hours are in local time, but DB stores appointments in UTC
-        let day_start_local = Rome.from_local_datetime(&day_start)
-            .single()
-            .ok_or_else(|| anyhow!("Invalid local start time"))?;
-        let day_end_local = Rome.from_local_datetime(&day_end)
-            .single()
-            .ok_or_else(|| anyhow!("Invalid local end time"))?;
-
-        let day_start_utc = day_start_local.with_timezone(&Utc);
-        let day_end_utc = day_end_local.with_timezone(&Utc);
-
-        // Get all appointments for this provider on this date
+        // Get all appointments for this provider on this date, and those
+        // whose buffers reach into it
         let booked_appointments = sqlx::query_as::<_, Appointment>(
             r#"
             SELECT * FROM appointments
             WHERE provider_id = $1
-              AND scheduled_start >= $2
-              AND scheduled_end <= $3
+              AND scheduled_start < $3
+              AND scheduled_end > $2
               AND status NOT IN ('CANCELLED', 'NO_SHOW')
             ORDER BY scheduled_start
             "#,
         )
         .bind(provider_id)
-        .bind(day_start_utc)
-        .bind(day_end_utc)
+        .bind(day_start_utc - reach)
+        .bind(day_end_utc + reach)
         .fetch_all(&self.pool)
         .await?;
 
-        // Generate time slots
-        let slot_duration = Duration::minutes(duration_minutes as i64);
-        let mut slots = Vec::new();
-        let mut current_time = day_start_utc;
-
-        while current_time < day_end_utc {
-            let slot_end = current_time + slot_duration;
-
-            // Check if this slot is during a break
-            let is_during_break = if let (Some((break_start_h, break_start_m)), Some((break_end_h, break_end_m))) = (break_start, break_end) {
-                // Convert slot time from UTC to local for break comparison (breaks are in local time)
-                let slot_time_local = current_time.with_timezone(&Rome).time();
-                let break_start_time = NaiveTime::from_hms_opt(break_start_h, break_start_m, 0)
-                    .unwrap_or(NaiveTime::from_hms_opt(12, 0, 0).unwrap());
-                let break_end_time = NaiveTime::from_hms_opt(break_end_h, break_end_m, 0)
-                    .unwrap_or(NaiveTime::from_hms_opt(13, 0, 0).unwrap());
-
-                slot_time_local >= break_start_time && slot_time_local < break_end_time
-            } else {
-                false
-            };
-
-            // Check if this slot conflicts with any booked appointment
-            let is_conflicted = booked_appointments.iter().any(|appt| {
-                // Check if slot overlaps with appointment
-                (current_time >= appt.scheduled_start && current_time < appt.scheduled_end)
-                    || (slot_end > appt.scheduled_start && slot_end <= appt.scheduled_end)
-                    || (current_time <= appt.scheduled_start && slot_end >= appt.scheduled_end)
-            });
+        let busy: Vec<_> = booked_appointments
+            .iter()
+            .map(|appt| {
+                buffers.busy(
+                    appt.appointment_type,
+                    appt.scheduled_start,
+                    appt.scheduled_end,
+                    appointment_type,
+                )
+            })
+            .collect();
+
+        let mut slots = day_time_slots(
+            &effective_hours,
+            (day_start_utc, day_end_utc),
+            &busy,
+            duration_minutes,
+        );
 
-            let is_available = !is_during_break && !is_conflicted;
+        // Reservations of the resources, by any provider
+        let mut conn = self.pool.acquire().await?;
+        bookable_resources(&mut conn, resource_ids).await?;
+        let slots_end = day_end_utc + Duration::minutes(duration_minutes as i64);
+        let bookings =
+            resource_bookings(&mut conn, resource_ids, day_start_utc, slots_end, None).await?;
+        let bottleneck = mark_busy_resources(&mut slots, resource_ids, &bookings);
 
-            slots.push(TimeSlot {
-                start: current_time,
-                end: slot_end,
-                available: is_available,
-            });
+        Ok((slots, bottleneck))
+    }
 
-            // Move to next slot (default 30 minute increments)
-            current_time = current_time + Duration::minutes(DEFAULT_SLOT_DURATION);
+    /// Check the availability of several providers on a given date
+    ///
+    /// Searches the active doctors in `provider_ids`, or all of them when it
+    /// is empty, ordered by name. Each provider's slots are computed as in
+    /// `check_availability`; resources stay shared between providers.
+    pub async fn check_availability_for_providers(
+        &self,
+        provider_ids: &[Uuid],
+        date: DateTime<Utc>,
+        duration_minutes: i32,
+        appointment_type: Option<AppointmentType>,
+        resource_ids: &[Uuid],
+    ) -> Result<Vec<ProviderAvailability>> {
+        let providers: Vec<(Uuid, String)> = sqlx::query_as(
+            r#"
+            SELECT id, first_name || ' ' || last_name FROM users
+            WHERE role = 'DOCTOR' AND is_active = true
+              AND (cardinality($1::UUID[]) = 0 OR id = ANY($1))
+            ORDER BY last_name, first_name
+            "#,
+        )
+        .bind(provider_ids)
+        .fetch_all(&self.pool)
+        .await?;
+
+        if let Some(missing) = provider_ids
+            .iter()
+            .find(|id| !providers.iter().any(|(provider_id, _)| provider_id == *id))
+        {
+            return Err(AppError::Validation(format!(
+                "Provider {} not found or not an active doctor",
+                missing
+            ))
+            .into());
         }
 
-        Ok(slots)
+        let mut availability = Vec::with_capacity(providers.len());
+        for (provider_id, provider_name) in providers {
+            let (slots, bottleneck_resource_id) = self
+                .check_availability(
+                    provider_id,
+                    date,
+                    duration_minutes,
+                    appointment_type,
+                    resource_ids,
+                )
+                .await?;
+            availability.push(ProviderAvailability {
+                provider_id,
+                provider_name,
+                slots,
+                bottleneck_resource_id,
+            });
+        }
+
+        Ok(availability)
     }
 
     /// Get daily schedule for a provider
@@ -1271,7 +2021,10 @@ impl AppointmentService {
         .fetch_all(&self.pool)
         .await?;
 
-        Ok(appointments.into_iter().map(|a| a.into()).collect())
+        appointments
+            .into_iter()
+            .map(|a| a.decrypt(&self.encryption_key))
+            .collect()
     }
 
     /// Get weekly schedule for a provider
@@ -1311,7 +2064,10 @@ impl AppointmentService {
         .fetch_all(&self.pool)
         .await?;
 
-        Ok(appointments.into_iter().map(|a| a.into()).collect())
+        appointments
+            .into_iter()
+            .map(|a| a.decrypt(&self.encryption_key))
+            .collect()
     }
 
     /// Get monthly schedule for a provider
@@ -1363,8 +2119,256 @@ impl AppointmentService {
         .fetch_all(&self.pool)
         .await?;
 
-        Ok(appointments.into_iter().map(|a| a.into()).collect())
+        appointments
+            .into_iter()
+            .map(|a| a.decrypt(&self.encryption_key))
+            .collect()
+    }
+}
+
+/// Replace the free text of an update with a placeholder in its audit log
+/// entry, so the audit log keeps which fields changed but not their text
+fn redact_free_text(changes: &mut serde_json::Value) {
+    for field in ["reason", "notes", "cancellation_reason"] {
+        if let Some(value) = changes.get_mut(field).filter(|v| !v.is_null()) {
+            *value = serde_json::Value::from(AUDIT_REDACTED_TEXT);
+        }
+    }
+}
+
+/// Working window of a day in UTC
+///
+/// Working hours are Europe/Rome local time; unconfigured or invalid times
+/// fall back to business hours.
+pub(crate) fn working_window(
+    effective_hours: &EffectiveWorkingHours,
+) -> Result<(DateTime<Utc>, DateTime<Utc>)> {
+    let date_naive = effective_hours.date;
+
+    // Parse working hours (with fallback to defaults)
+    let (start_hour, start_min) = match &effective_hours.start_time {
+        Some(time) => parse_time_str(time).unwrap_or((FALLBACK_START_HOUR, 0)),
+        None => (FALLBACK_START_HOUR, 0),
+    };
+
+    let (end_hour, end_min) = match &effective_hours.end_time {
+        Some(time) => parse_time_str(time).unwrap_or((FALLBACK_END_HOUR, 0)),
+        None => (FALLBACK_END_HOUR, 0),
+    };
+
+    let day_start = date_naive.and_hms_opt(start_hour, start_min, 0)
+        .ok_or_else(|| anyhow!("Invalid start time"))?;
+    let day_end = date_naive.and_hms_opt(end_hour, end_min, 0)
+        .ok_or_else(|| anyhow!("Invalid end time"))?;
+
+    // Convert local times (Europe/Rome) to UTC for database queries
+    // Working hours are in local time, but DB stores appointments in UTC
+    let day_start_local = Rome.from_local_datetime(&day_start)
+        .single()
+        .ok_or_else(|| anyhow!("Invalid local start time"))?;
+    let day_end_local = Rome.from_local_datetime(&day_end)
+        .single()
+        .ok_or_else(|| anyhow!("Invalid local end time"))?;
+
+    Ok((
+        day_start_local.with_timezone(&Utc),
+        day_end_local.with_timezone(&Utc),
+    ))
+}
+
+/// Time slots of a working day, every 30 minutes across `window`
+///
+/// A slot is unavailable when it starts during the break or overlaps one of
+/// the `busy` intervals.
+pub(crate) fn day_time_slots(
+    effective_hours: &EffectiveWorkingHours,
+    window: (DateTime<Utc>, DateTime<Utc>),
+    busy: &[(DateTime<Utc>, DateTime<Utc>)],
+    duration_minutes: i32,
+) -> Vec<TimeSlot> {
+    let (day_start_utc, day_end_utc) = window;
+
+    // Parse break times if configured
+    let break_start = effective_hours.break_start.as_ref().and_then(|t| parse_time_str(t));
+    let break_end = effective_hours.break_end.as_ref().and_then(|t| parse_time_str(t));
+
+    // Generate time slots
+    let slot_duration = Duration::minutes(duration_minutes as i64);
+    let mut slots = Vec::new();
+    let mut current_time = day_start_utc;
+
+    while current_time < day_end_utc {
+        let slot_end = current_time + slot_duration;
+
+        // Check if this slot is during a break
+        let is_during_break = if let (Some((break_start_h, break_start_m)), Some((break_end_h, break_end_m))) = (break_start, break_end) {
+            // Convert slot time from UTC to local for break comparison (breaks are in local time)
+            let slot_time_local = current_time.with_timezone(&Rome).time();
+            let break_start_time = NaiveTime::from_hms_opt(break_start_h, break_start_m, 0)
+                .unwrap_or(NaiveTime::from_hms_opt(12, 0, 0).unwrap());
+            let break_end_time = NaiveTime::from_hms_opt(break_end_h, break_end_m, 0)
+                .unwrap_or(NaiveTime::from_hms_opt(13, 0, 0).unwrap());
+
+            slot_time_local >= break_start_time && slot_time_local < break_end_time
+        } else {
+            false
+        };
+
+        // Check if this slot conflicts with any booked appointment
+        let is_conflicted = busy.iter().any(|&(busy_start, busy_end)| {
+            // Check if slot overlaps with appointment
+            (current_time >= busy_start && current_time < busy_end)
+                || (slot_end > busy_start && slot_end <= busy_end)
+                || (current_time <= busy_start && slot_end >= busy_end)
+        });
+
+        let is_available = !is_during_break && !is_conflicted;
+
+        slots.push(TimeSlot {
+            start: current_time,
+            end: slot_end,
+            available: is_available,
+            busy_resources: Vec::new(),
+        });
+
+        // Move to next slot (default 30 minute increments)
+        current_time = current_time + Duration::minutes(DEFAULT_SLOT_DURATION);
     }
+
+    slots
+}
+
+/// Check an appointment time against the effective working hours of its day
+///
+/// Returns why it cannot take place: a non-working day, outside working
+/// hours, or overlapping the break. Working hours are Europe/Rome local
+/// time; unconfigured or invalid times fall back to business hours.
+pub(crate) fn working_hours_conflict(
+    effective_hours: &EffectiveWorkingHours,
+    scheduled_start: DateTime<Utc>,
+    scheduled_end: DateTime<Utc>,
+) -> Option<SchedulingConflict> {
+    // Check if it's a working day
+    if !effective_hours.is_working_day {
+        return Some(SchedulingConflict {
+            kind: SchedulingConflictKind::NonWorkingDay,
+            message: format!(
+                "Cannot schedule appointment on a non-working day ({})",
+                effective_hours.date.format("%Y-%m-%d")
+            ),
+            start: None,
+            end: None,
+            appointment_id: None,
+            resource_id: None,
+        });
+    }
+
+    // Parse working hours
+    let start_time = effective_hours
+        .start_time
+        .as_ref()
+        .and_then(|t| parse_time_str(t))
+        .unwrap_or((FALLBACK_START_HOUR, 0));
+
+    let end_time = effective_hours
+        .end_time
+        .as_ref()
+        .and_then(|t| parse_time_str(t))
+        .unwrap_or((FALLBACK_END_HOUR, 0));
+
+    // Get appointment times in local timezone (Europe/Rome)
+    // Working hours are defined in local time, so we must convert UTC to local for comparison
+    let appt_start_time = scheduled_start.with_timezone(&Rome).time();
+    let appt_end_time = scheduled_end.with_timezone(&Rome).time();
+
+    let working_start = NaiveTime::from_hms_opt(start_time.0, start_time.1, 0)
+        .unwrap_or(NaiveTime::from_hms_opt(8, 0, 0).unwrap());
+    let working_end = NaiveTime::from_hms_opt(end_time.0, end_time.1, 0)
+        .unwrap_or(NaiveTime::from_hms_opt(18, 0, 0).unwrap());
+
+    // Check if appointment is within working hours
+    if appt_start_time < working_start || appt_end_time > working_end {
+        return Some(SchedulingConflict {
+            kind: SchedulingConflictKind::OutsideWorkingHours,
+            message: format!(
+                "Appointment time ({} - {}) is outside working hours ({} - {})",
+                appt_start_time.format("%H:%M"),
+                appt_end_time.format("%H:%M"),
+                working_start.format("%H:%M"),
+                working_end.format("%H:%M")
+            ),
+            start: local_to_utc(effective_hours.date, working_start),
+            end: local_to_utc(effective_hours.date, working_end),
+            appointment_id: None,
+            resource_id: None,
+        });
+    }
+
+    // Check if appointment overlaps with break time
+    if let (Some((break_start_h, break_start_m)), Some((break_end_h, break_end_m))) = (
+        effective_hours.break_start.as_ref().and_then(|t| parse_time_str(t)),
+        effective_hours.break_end.as_ref().and_then(|t| parse_time_str(t)),
+    ) {
+        let break_start = NaiveTime::from_hms_opt(break_start_h, break_start_m, 0)
+            .unwrap_or(NaiveTime::from_hms_opt(12, 0, 0).unwrap());
+        let break_end = NaiveTime::from_hms_opt(break_end_h, break_end_m, 0)
+            .unwrap_or(NaiveTime::from_hms_opt(13, 0, 0).unwrap());
+
+        // Overlap occurs if: appt_start < break_end AND appt_end > break_start
+        if appt_start_time < break_end && appt_end_time > break_start {
+            return Some(SchedulingConflict {
+                kind: SchedulingConflictKind::BreakTime,
+                message: format!(
+                    "Appointment time ({} - {}) overlaps with break time ({} - {})",
+                    appt_start_time.format("%H:%M"),
+                    appt_end_time.format("%H:%M"),
+                    break_start.format("%H:%M"),
+                    break_end.format("%H:%M")
+                ),
+                start: local_to_utc(effective_hours.date, break_start),
+                end: local_to_utc(effective_hours.date, break_end),
+                appointment_id: None,
+                resource_id: None,
+            });
+        }
+    }
+
+    None
+}
+
+/// Europe/Rome local time of a day in UTC
+fn local_to_utc(date: NaiveDate, time: NaiveTime) -> Option<DateTime<Utc>> {
+    Rome.from_local_datetime(&date.and_time(time))
+        .single()
+        .map(|local| local.with_timezone(&Utc))
+}
+
+/// Error refusing a time: why, the free times suggested instead, and
+/// whether it can be overbooked
+fn scheduling_conflict(
+    conflicts: &[SchedulingConflict],
+    alternatives: &[AlternativeSlot],
+    overbookable: bool,
+) -> AppError {
+    AppError::SchedulingConflict(serde_json::json!({
+        "message": conflicts.first().map(|conflict| conflict.message.as_str()),
+        "conflicts": conflicts,
+        "alternatives": alternatives,
+        "overbookable": overbookable,
+    }))
+}
+
+/// The `count` slots starting nearest to `requested_start` (the earlier one
+/// on a tie), in time order
+fn nearest_slots(
+    mut slots: Vec<AlternativeSlot>,
+    requested_start: DateTime<Utc>,
+    count: usize,
+) -> Vec<AlternativeSlot> {
+    slots.sort_by_key(|slot| ((slot.start - requested_start).num_seconds().abs(), slot.start));
+    slots.truncate(count);
+    slots.sort_by_key(|slot| slot.start);
+    slots
 }
 
 /// Helper to parse time string "HH:MM" into (hour, minute)
@@ -1470,6 +2474,23 @@ mod tests {
         assert_eq!(parse_time_str("08:5"), Some((8, 5)));
     }
 
+    // ==================== redact_free_text Tests ====================
+
+    #[test]
+    fn test_redact_free_text_keeps_changed_fields() {
+        let mut changes = serde_json::json!({
+            "reason": "Persistent cough",
+            "notes": null,
+            "status": "CONFIRMED",
+        });
+        redact_free_text(&mut changes);
+
+        assert_eq!(changes["reason"], AUDIT_REDACTED_TEXT);
+        assert!(changes["notes"].is_null());
+        assert_eq!(changes["status"], "CONFIRMED");
+        assert!(changes.get("cancellation_reason").is_none());
+    }
+
     // ==================== Constants Tests ====================
 
     #[test]
@@ -1869,4 +2890,75 @@ mod tests {
         let is_within = appt_start >= working_start && appt_end <= working_end;
         assert!(is_within);
     }
+
+    // ==================== Scheduling Conflict Tests ====================
+
+    fn working_day(date: &str) -> EffectiveWorkingHours {
+        let date: NaiveDate = date.parse().unwrap();
+        EffectiveWorkingHours {
+            date,
+            day_of_week: 1,
+            day_name: "Monday".to_string(),
+            is_working_day: true,
+            start_time: Some("09:00".to_string()),
+            end_time: Some("17:00".to_string()),
+            break_start: Some("13:00".to_string()),
+            break_end: Some("14:00".to_string()),
+            is_override: false,
+            source: "DEFAULT".to_string(),
+        }
+    }
+
+    fn rome(date: &str, hour: u32, minute: u32) -> DateTime<Utc> {
+        let date: NaiveDate = date.parse().unwrap();
+        local_to_utc(date, NaiveTime::from_hms_opt(hour, minute, 0).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_working_hours_conflict_kinds() {
+        let hours = working_day("2026-05-11");
+        let at = |hour, minute| rome("2026-05-11", hour, minute);
+
+        assert!(working_hours_conflict(&hours, at(9, 0), at(9, 30)).is_none());
+        assert!(working_hours_conflict(&hours, at(16, 30), at(17, 0)).is_none());
+
+        let conflict = working_hours_conflict(&hours, at(16, 30), at(17, 30)).unwrap();
+        assert_eq!(conflict.kind, SchedulingConflictKind::OutsideWorkingHours);
+        assert_eq!(conflict.start, Some(at(9, 0)));
+        assert_eq!(conflict.end, Some(at(17, 0)));
+
+        let conflict = working_hours_conflict(&hours, at(12, 30), at(13, 30)).unwrap();
+        assert_eq!(conflict.kind, SchedulingConflictKind::BreakTime);
+        assert_eq!(conflict.start, Some(at(13, 0)));
+
+        let closed = EffectiveWorkingHours {
+            is_working_day: false,
+            ..hours
+        };
+        let conflict = working_hours_conflict(&closed, at(9, 0), at(9, 30)).unwrap();
+        assert_eq!(conflict.kind, SchedulingConflictKind::NonWorkingDay);
+        assert!(conflict.start.is_none());
+    }
+
+    #[test]
+    fn test_nearest_slots_in_time_order() {
+        let requested = rome("2026-05-11", 10, 0);
+        let slot = |hour| AlternativeSlot {
+            start: rome("2026-05-11", hour, 0),
+            end: rome("2026-05-11", hour, 30),
+        };
+        let next_day = AlternativeSlot {
+            start: rome("2026-05-12", 10, 0),
+            end: rome("2026-05-12", 10, 30),
+        };
+
+        let nearest = nearest_slots(
+            vec![next_day, slot(15), slot(9), slot(11), slot(12)],
+            requested,
+            MAX_ALTERNATIVE_SLOTS,
+        );
+        assert_eq!(nearest, vec![slot(9), slot(11), slot(12)]);
+
+        assert!(nearest_slots(Vec::new(), requested, MAX_ALTERNATIVE_SLOTS).is_empty());
+    }
 }
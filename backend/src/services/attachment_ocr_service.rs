@@ -0,0 +1,324 @@
+/*!
+ * Attachment OCR Service
+ *
+ * Background job recognizing the text of patient attachments with the
+ * configured `OcrEngine`. The text is stored encrypted, makes attachments
+ * searchable, and is scanned for fields to suggest to the user: the
+ * document date and the issuing laboratory.
+ *
+ * Attachments are claimed with `FOR UPDATE SKIP LOCKED`, so several
+ * instances can run the job. One left PROCESSING by a crashed instance is
+ * claimed again after `STALE_AFTER_MINUTES`. Failures are retried on later
+ * runs up to `MAX_ATTEMPTS`; files the engine cannot read are SKIPPED.
+ */
+
+use crate::db::rls::{apply_rls_context, SYSTEM_ROLE, SYSTEM_USER_ID};
+use crate::models::{OcrStatus, OcrSuggestions};
+use crate::services::{ocr_engine::OcrEngine, FileUploadService};
+use crate::utils::encryption::EncryptionKey;
+use chrono::{Datelike, NaiveDate, Utc};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use sqlx::{PgPool, Postgres, Transaction};
+use std::sync::Arc;
+use tokio::time::{sleep, Duration};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// Pause between runs
+const POLL_INTERVAL_SECS: u64 = 30;
+
+/// Attachments processed per run
+const BATCH_SIZE: i64 = 10;
+
+/// Attempts before an attachment is marked FAILED
+const MAX_ATTEMPTS: i32 = 3;
+
+/// PROCESSING attachments older than this are claimed again
+const STALE_AFTER_MINUTES: i32 = 30;
+
+/// Longest suggested laboratory name
+const MAX_LAB_NAME_LENGTH: usize = 120;
+
+/// dd/mm/yyyy, dd-mm-yyyy, dd.mm.yyyy
+static DMY_DATE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\b(\d{1,2})[/.\-](\d{1,2})[/.\-](\d{4})\b").unwrap());
+
+/// yyyy-mm-dd
+static ISO_DATE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\b(\d{4})-(\d{2})-(\d{2})\b").unwrap());
+
+/// Lines with the patient's date of birth
+const BIRTH_KEYWORDS: &[&str] = &["nascita", "nato il", "nata il", "birth", "d.o.b"];
+
+/// Lines naming the issuing laboratory
+const LAB_KEYWORDS: &[&str] = &[
+    "laboratorio",
+    "laboratory",
+    "analisi cliniche",
+    "centro diagnostico",
+    "poliambulatorio",
+];
+
+/// Suggest the document date and laboratory from recognized text
+///
+/// The date is the first plausible one (not in the future, not before 1900)
+/// on a line that is not a date of birth; the laboratory is the first line
+/// naming one.
+pub fn suggest_fields(text: &str) -> OcrSuggestions {
+    let today = Utc::now().date_naive();
+    let mut suggestions = OcrSuggestions::default();
+
+    for line in text.lines() {
+        let lower = line.to_lowercase();
+
+        if suggestions.document_date.is_none() && !BIRTH_KEYWORDS.iter().any(|k| lower.contains(k))
+        {
+            suggestions.document_date = find_date(line).filter(|d| *d <= today);
+        }
+
+        if suggestions.lab_name.is_none() && LAB_KEYWORDS.iter().any(|k| lower.contains(k)) {
+            let name: String = line.trim().chars().take(MAX_LAB_NAME_LENGTH).collect();
+            suggestions.lab_name = Some(name);
+        }
+
+        if suggestions.document_date.is_some() && suggestions.lab_name.is_some() {
+            break;
+        }
+    }
+
+    suggestions
+}
+
+fn find_date(line: &str) -> Option<NaiveDate> {
+    let dmy = DMY_DATE.captures_iter(line).filter_map(|c| {
+        NaiveDate::from_ymd_opt(c[3].parse().ok()?, c[2].parse().ok()?, c[1].parse().ok()?)
+    });
+    let iso = ISO_DATE.captures_iter(line).filter_map(|c| {
+        NaiveDate::from_ymd_opt(c[1].parse().ok()?, c[2].parse().ok()?, c[3].parse().ok()?)
+    });
+
+    dmy.chain(iso).find(|d| d.year() >= 1900)
+}
+
+/// Attachment claimed for recognition
+#[derive(sqlx::FromRow)]
+struct OcrJob {
+    id: Uuid,
+    file_id: Uuid,
+    ocr_attempts: i32,
+}
+
+/// Attachment OCR job
+pub struct AttachmentOcrService {
+    pool: PgPool,
+    encryption_key: EncryptionKey,
+    engine: Arc<dyn OcrEngine>,
+}
+
+impl AttachmentOcrService {
+    /// Create a new attachment OCR service
+    pub fn new(pool: PgPool, encryption_key: EncryptionKey, engine: Arc<dyn OcrEngine>) -> Self {
+        Self {
+            pool,
+            encryption_key,
+            engine,
+        }
+    }
+
+    /// Recognize a batch of queued attachments
+    ///
+    /// Returns how many attachments were claimed.
+    pub async fn run_batch(&self) -> anyhow::Result<usize> {
+        let mut tx = self.begin_system_tx().await?;
+        let jobs = sqlx::query_as::<_, OcrJob>(
+            r#"
+            UPDATE patient_attachments
+            SET ocr_status = 'PROCESSING', ocr_started_at = NOW(),
+                ocr_attempts = ocr_attempts + 1
+            WHERE id IN (
+                SELECT id FROM patient_attachments
+                WHERE deleted_at IS NULL
+                  AND (ocr_status = 'PENDING'
+                       OR (ocr_status = 'PROCESSING'
+                           AND ocr_started_at < NOW() - make_interval(mins => $2)))
+                ORDER BY created_at
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, file_id, ocr_attempts
+            "#,
+        )
+        .bind(BATCH_SIZE)
+        .bind(STALE_AFTER_MINUTES)
+        .fetch_all(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        for job in &jobs {
+            if let Err(e) = self.process(job).await {
+                let status = if job.ocr_attempts >= MAX_ATTEMPTS {
+                    OcrStatus::Failed
+                } else {
+                    OcrStatus::Pending
+                };
+                warn!(
+                    "OCR of attachment {} failed (attempt {}): {:#}",
+                    job.id, job.ocr_attempts, e
+                );
+                let message = format!("{:#}", e);
+                if let Err(e) = self
+                    .finish(job.id, status, None, None, Some(&message))
+                    .await
+                {
+                    error!(
+                        "Failed to record OCR failure of attachment {}: {}",
+                        job.id, e
+                    );
+                }
+            }
+        }
+
+        Ok(jobs.len())
+    }
+
+    async fn process(&self, job: &OcrJob) -> anyhow::Result<()> {
+        let (file, content) = FileUploadService::get_decrypted_file_with_content(
+            &self.pool,
+            job.file_id,
+            &self.encryption_key,
+        )
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?
+        .ok_or_else(|| anyhow::anyhow!("File {} is missing", job.file_id))?;
+
+        if !self.engine.supports(&file.mime_type) {
+            return self
+                .finish(job.id, OcrStatus::Skipped, None, None, None)
+                .await;
+        }
+
+        let text = self.engine.recognize(&content, &file.mime_type).await?;
+        let text = text.trim();
+        let suggestions = suggest_fields(text);
+
+        let encrypted_text = self.encryption_key.encrypt(text)?;
+        let encrypted_suggestions = self.encryption_key.encrypt_json(&suggestions)?;
+
+        self.finish(
+            job.id,
+            OcrStatus::Completed,
+            Some(&encrypted_text),
+            Some(&encrypted_suggestions),
+            None,
+        )
+        .await?;
+
+        info!(
+            "OCR of attachment {} completed with {} ({} characters)",
+            job.id,
+            self.engine.name(),
+            text.len()
+        );
+        Ok(())
+    }
+
+    /// Record the outcome, unless the attachment was re-queued meanwhile
+    async fn finish(
+        &self,
+        attachment_id: Uuid,
+        status: OcrStatus,
+        encrypted_text: Option<&str>,
+        encrypted_suggestions: Option<&str>,
+        error_message: Option<&str>,
+    ) -> anyhow::Result<()> {
+        let mut tx = self.begin_system_tx().await?;
+        sqlx::query(
+            r#"
+            UPDATE patient_attachments
+            SET ocr_status = $2, ocr_text = $3, ocr_suggestions = $4, ocr_error = $5,
+                ocr_completed_at = CASE WHEN $2 = 'PENDING' THEN NULL ELSE NOW() END
+            WHERE id = $1 AND ocr_status = 'PROCESSING'
+            "#,
+        )
+        .bind(attachment_id)
+        .bind(status.as_str())
+        .bind(encrypted_text)
+        .bind(encrypted_suggestions)
+        .bind(error_message)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn begin_system_tx(&self) -> Result<Transaction<'static, Postgres>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        apply_rls_context(&mut tx, SYSTEM_USER_ID, SYSTEM_ROLE).await?;
+        Ok(tx)
+    }
+}
+
+/// Spawn the attachment OCR job as a background task
+///
+/// Runs every `POLL_INTERVAL_SECS`, and straight again while there is a
+/// backlog.
+pub fn spawn_attachment_ocr_job(
+    pool: PgPool,
+    encryption_key: EncryptionKey,
+    engine: Arc<dyn OcrEngine>,
+) {
+    let engine_name = engine.name();
+    let service = AttachmentOcrService::new(pool, encryption_key, engine);
+
+    tokio::spawn(async move {
+        loop {
+            match service.run_batch().await {
+                Ok(claimed) if claimed as i64 == BATCH_SIZE => continue,
+                Ok(_) => {}
+                Err(e) => error!("Attachment OCR job failed: {}", e),
+            }
+            sleep(Duration::from_secs(POLL_INTERVAL_SECS)).await;
+        }
+    });
+
+    info!(
+        "Attachment OCR job spawned as background task ({})",
+        engine_name
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suggest_fields_from_lab_report() {
+        let text = "LABORATORIO ANALISI SAN MARCO S.r.l.\n\
+                    Paziente: ROSSI MARIO  Data di nascita: 12/05/1961\n\
+                    Data referto: 03.11.2025\n\
+                    Glicemia 98 mg/dL";
+
+        let suggestions = suggest_fields(text);
+        assert_eq!(
+            suggestions.document_date,
+            NaiveDate::from_ymd_opt(2025, 11, 3)
+        );
+        assert_eq!(
+            suggestions.lab_name.as_deref(),
+            Some("LABORATORIO ANALISI SAN MARCO S.r.l.")
+        );
+    }
+
+    #[test]
+    fn test_suggest_fields_ignores_implausible_dates() {
+        let suggestions =
+            suggest_fields("Report 31/02/2024\nValid until 01/01/2999\nIssued 2024-06-15");
+        assert_eq!(
+            suggestions.document_date,
+            NaiveDate::from_ymd_opt(2024, 6, 15)
+        );
+        assert_eq!(suggestions.lab_name, None);
+
+        assert_eq!(suggest_fields(""), OcrSuggestions::default());
+    }
+}
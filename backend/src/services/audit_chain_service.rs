@@ -0,0 +1,500 @@
+/*!
+ * Audit Chain Service
+ *
+ * Verifies the tamper-evident hash chain over `audit_logs`.
+ *
+ * A database trigger assigns every new row a `chain_seq` and a `row_hash`
+ * (SHA-256 over the previous row's hash and the row contents) and records
+ * the latest pair in `audit_log_chain_head`. Verification walks the chain in
+ * sequence order, recomputes every hash here rather than in SQL, and reports:
+ * - gaps and duplicate sequence numbers (rows deleted or inserted)
+ * - rows whose prev_hash does not link to the previous row
+ * - rows whose contents no longer match their hash (rows modified)
+ * - a chain that ends before the head (rows deleted from the end)
+ *
+ * Partitions dropped by the audit log retention rule are recorded in the
+ * head as `pruned_seq`/`pruned_hash`; verification starts after them.
+ *
+ * Runs on demand via `GET /api/v1/audit-logs/verify` and periodically in the
+ * background; a broken chain raises a critical system alert.
+ */
+
+use crate::models::audit_log::{AuditChainIssue, AuditChainIssueKind, AuditChainReport};
+use crate::models::{AlertSeverity, CreateSystemAlert, SystemAlert};
+use crate::services::SettingsService;
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::sync::Arc;
+use tokio::time::{sleep, Duration as TokioDuration};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// prev_hash of the first row in the chain
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Rows fetched per verification query
+const VERIFY_BATCH_SIZE: i64 = 5000;
+
+/// Issues listed in a report; the total is still counted past this
+const MAX_REPORTED_ISSUES: usize = 100;
+
+/// Default hours between background verifications
+const DEFAULT_VERIFY_INTERVAL_HOURS: i64 = 24;
+
+/// Source recorded on alerts raised by the verifier
+const ALERT_SOURCE: &str = "audit_chain";
+
+/// Chained audit log row, with the fields whose text form is hashed
+/// already rendered by PostgreSQL
+#[derive(Debug, Clone, sqlx::FromRow)]
+struct ChainedRow {
+    id: i64,
+    chain_seq: i64,
+    prev_hash: String,
+    row_hash: String,
+    user_id: Option<Uuid>,
+    action: String,
+    entity_type: String,
+    entity_id: Option<String>,
+    changes: Option<String>,
+    ip_address: Option<String>,
+    user_agent: Option<String>,
+    request_id: Option<Uuid>,
+    impersonator_id: Option<Uuid>,
+    created_at: DateTime<Utc>,
+}
+
+impl ChainedRow {
+    /// Canonical encoding hashed by the `chain_audit_log()` trigger
+    fn canonical(&self) -> String {
+        let seq = self.chain_seq.to_string();
+        let user_id = self.user_id.map(|id| id.to_string());
+        let request_id = self.request_id.map(|id| id.to_string());
+        let impersonator_id = self.impersonator_id.map(|id| id.to_string());
+        let created_at = self.created_at.format("%Y-%m-%dT%H:%M:%S%.6fZ").to_string();
+
+        [
+            Some(self.prev_hash.as_str()),
+            Some(seq.as_str()),
+            user_id.as_deref(),
+            Some(self.action.as_str()),
+            Some(self.entity_type.as_str()),
+            self.entity_id.as_deref(),
+            self.changes.as_deref(),
+            self.ip_address.as_deref(),
+            self.user_agent.as_deref(),
+            request_id.as_deref(),
+            impersonator_id.as_deref(),
+            Some(created_at.as_str()),
+        ]
+        .iter()
+        .map(|value| chain_field(*value))
+        .collect::<Vec<_>>()
+        .join("|")
+    }
+
+    /// Recompute the row hash from its contents
+    fn compute_hash(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.canonical().as_bytes());
+        format!("{:x}", hasher.finalize())
+    }
+}
+
+/// Length-prefixed field, or "-" for NULL
+fn chain_field(value: Option<&str>) -> String {
+    match value {
+        Some(v) => format!("{}:{}", v.len(), v),
+        None => "-".to_string(),
+    }
+}
+
+/// Accumulates issues while walking the chain
+#[derive(Debug, Default)]
+struct ChainWalk {
+    rows_checked: i64,
+    issue_count: i64,
+    issues: Vec<AuditChainIssue>,
+    /// Sequence number and hash of the last row checked
+    last: Option<(i64, String)>,
+}
+
+impl ChainWalk {
+    /// Start a walk after the last row removed by retention
+    fn after_pruned(pruned_seq: i64, pruned_hash: String) -> Self {
+        Self {
+            last: (pruned_seq > 0).then_some((pruned_seq, pruned_hash)),
+            ..Default::default()
+        }
+    }
+
+    fn report(
+        &mut self,
+        kind: AuditChainIssueKind,
+        chain_seq: i64,
+        audit_log_id: Option<i64>,
+        detail: String,
+    ) {
+        self.issue_count += 1;
+        if self.issues.len() < MAX_REPORTED_ISSUES {
+            self.issues.push(AuditChainIssue {
+                kind,
+                chain_seq,
+                audit_log_id,
+                detail,
+            });
+        }
+    }
+
+    /// Check one row against the row before it
+    fn check(&mut self, row: &ChainedRow) {
+        let (prev_seq, prev_hash) = self.last.clone().unwrap_or((0, GENESIS_HASH.to_string()));
+        let expected_seq = prev_seq + 1;
+
+        if row.chain_seq < expected_seq {
+            self.report(
+                AuditChainIssueKind::DuplicateSequence,
+                row.chain_seq,
+                Some(row.id),
+                format!("Sequence {} appears more than once", row.chain_seq),
+            );
+        } else if row.chain_seq > expected_seq {
+            // The link is necessarily broken across a gap; report the gap only
+            let detail = if row.chain_seq - 1 == expected_seq {
+                format!("Sequence {} is missing", expected_seq)
+            } else {
+                format!(
+                    "Sequences {} to {} are missing",
+                    expected_seq,
+                    row.chain_seq - 1
+                )
+            };
+            self.report(AuditChainIssueKind::Gap, expected_seq, Some(row.id), detail);
+        } else if row.prev_hash != prev_hash {
+            self.report(
+                AuditChainIssueKind::BrokenLink,
+                row.chain_seq,
+                Some(row.id),
+                format!("prev_hash does not match the hash of sequence {}", prev_seq),
+            );
+        }
+
+        if row.compute_hash() != row.row_hash {
+            self.report(
+                AuditChainIssueKind::HashMismatch,
+                row.chain_seq,
+                Some(row.id),
+                "Row contents do not match row_hash".to_string(),
+            );
+        }
+
+        self.rows_checked += 1;
+        self.last = Some((row.chain_seq, row.row_hash.clone()));
+    }
+
+    /// Check that the chain ends at the recorded head
+    fn finish(&mut self, head_seq: i64, head_hash: &str) {
+        let (last_seq, last_hash) = self.last.clone().unwrap_or((0, GENESIS_HASH.to_string()));
+
+        if last_seq != head_seq {
+            self.report(
+                AuditChainIssueKind::HeadMismatch,
+                last_seq,
+                None,
+                format!(
+                    "Chain ends at sequence {} but the head records sequence {}",
+                    last_seq, head_seq
+                ),
+            );
+        } else if last_hash != head_hash {
+            self.report(
+                AuditChainIssueKind::HeadMismatch,
+                last_seq,
+                None,
+                "Hash of the last row does not match the head".to_string(),
+            );
+        }
+    }
+}
+
+/// Service for verifying the audit log hash chain
+pub struct AuditChainService {
+    pool: PgPool,
+}
+
+impl AuditChainService {
+    /// Create a new AuditChainService instance
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Recompute the whole chain and report any integrity problems
+    ///
+    /// Only rows up to the head read at the start are checked, so audit
+    /// entries written during verification do not cause false positives.
+    pub async fn verify(&self) -> Result<AuditChainReport, sqlx::Error> {
+        let (head_seq, head_hash, pruned_seq, pruned_hash): (i64, String, i64, String) =
+            sqlx::query_as(
+                "SELECT last_seq, last_hash, pruned_seq, pruned_hash FROM audit_log_chain_head",
+            )
+            .fetch_one(&self.pool)
+            .await?;
+
+        let unchained_rows: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM audit_logs WHERE chain_seq IS NULL")
+                .fetch_one(&self.pool)
+                .await?;
+
+        let mut walk = ChainWalk::after_pruned(pruned_seq, pruned_hash);
+        let mut cursor: (i64, i64) = (pruned_seq, i64::MAX);
+
+        loop {
+            let rows = sqlx::query_as::<_, ChainedRow>(
+                r#"
+                SELECT
+                    id, chain_seq, prev_hash, row_hash,
+                    user_id, action, entity_type, entity_id,
+                    changes::TEXT AS changes,
+                    ip_address::TEXT AS ip_address,
+                    user_agent, request_id, impersonator_id, created_at
+                FROM audit_logs
+                WHERE chain_seq IS NOT NULL
+                  AND chain_seq <= $1
+                  AND (chain_seq, id) > ($2, $3)
+                ORDER BY chain_seq, id
+                LIMIT $4
+                "#,
+            )
+            .bind(head_seq)
+            .bind(cursor.0)
+            .bind(cursor.1)
+            .bind(VERIFY_BATCH_SIZE)
+            .fetch_all(&self.pool)
+            .await?;
+
+            for row in &rows {
+                walk.check(row);
+            }
+
+            match rows.last() {
+                Some(last) if rows.len() as i64 == VERIFY_BATCH_SIZE => {
+                    cursor = (last.chain_seq, last.id);
+                }
+                _ => break,
+            }
+        }
+
+        walk.finish(head_seq, &head_hash);
+
+        Ok(AuditChainReport {
+            valid: walk.issue_count == 0,
+            rows_checked: walk.rows_checked,
+            unchained_rows,
+            head_seq,
+            pruned_seq,
+            issue_count: walk.issue_count,
+            issues: walk.issues,
+            verified_at: Utc::now(),
+        })
+    }
+
+    /// Verify the chain and raise a critical alert if it is broken
+    async fn verify_and_alert(&self) {
+        match self.verify().await {
+            Ok(report) if report.valid => info!(
+                "Audit log chain verified: {} rows up to sequence {}",
+                report.rows_checked, report.head_seq
+            ),
+            Ok(report) => {
+                error!(
+                    "Audit log chain verification found {} issues",
+                    report.issue_count
+                );
+                let alert = CreateSystemAlert {
+                    alert_type: "AUDIT_CHAIN_BROKEN",
+                    severity: AlertSeverity::Critical,
+                    source: ALERT_SOURCE,
+                    entity_type: None,
+                    entity_id: None,
+                    message: format!(
+                        "Audit log hash chain verification found {} issues; audit records may have been altered or deleted",
+                        report.issue_count
+                    ),
+                    details: serde_json::to_value(&report).ok(),
+                };
+                if let Err(e) = SystemAlert::create(&self.pool, alert).await {
+                    error!("Failed to record system alert: {}", e);
+                }
+            }
+            Err(e) => warn!("Audit log chain verification failed: {}", e),
+        }
+    }
+}
+
+/// Spawn periodic chain verification as a background task
+///
+/// The interval is read from `audit_chain_verify_interval_hours` before each
+/// run; 0 disables verification until the setting changes.
+pub fn spawn_audit_chain_verifier(pool: PgPool, settings_service: Arc<SettingsService>) {
+    let service = AuditChainService::new(pool);
+
+    tokio::spawn(async move {
+        loop {
+            let interval_hours = match settings_service
+                .get_setting("audit_chain_verify_interval_hours")
+                .await
+            {
+                Ok(Some(setting)) => setting
+                    .setting_value
+                    .as_i64()
+                    .unwrap_or(DEFAULT_VERIFY_INTERVAL_HOURS),
+                _ => DEFAULT_VERIFY_INTERVAL_HOURS,
+            };
+
+            if interval_hours <= 0 {
+                sleep(TokioDuration::from_secs(3600)).await;
+                continue;
+            }
+
+            sleep(TokioDuration::from_secs(interval_hours as u64 * 3600)).await;
+            service.verify_and_alert().await;
+        }
+    });
+
+    info!("Audit chain verifier spawned as background task");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    /// Build a correctly linked row after `prev`
+    fn chained(prev: Option<&ChainedRow>, id: i64) -> ChainedRow {
+        let mut row = ChainedRow {
+            id,
+            chain_seq: prev.map_or(1, |p| p.chain_seq + 1),
+            prev_hash: prev.map_or(GENESIS_HASH.to_string(), |p| p.row_hash.clone()),
+            row_hash: String::new(),
+            user_id: Some(Uuid::from_u128(id as u128)),
+            action: "UPDATE".to_string(),
+            entity_type: "PATIENT".to_string(),
+            entity_id: Some("a|b".to_string()),
+            changes: Some(r#"{"field": "value"}"#.to_string()),
+            ip_address: Some("192.168.1.10/32".to_string()),
+            user_agent: None,
+            request_id: None,
+            impersonator_id: None,
+            created_at: Utc.with_ymd_and_hms(2026, 3, 19, 10, 30, 0).unwrap(),
+        };
+        row.row_hash = row.compute_hash();
+        row
+    }
+
+    fn walk(rows: &[ChainedRow], head: &ChainedRow) -> ChainWalk {
+        walk_from(ChainWalk::default(), rows, head)
+    }
+
+    fn walk_from(mut walk: ChainWalk, rows: &[ChainedRow], head: &ChainedRow) -> ChainWalk {
+        for row in rows {
+            walk.check(row);
+        }
+        walk.finish(head.chain_seq, &head.row_hash);
+        walk
+    }
+
+    fn kinds(walk: &ChainWalk) -> Vec<AuditChainIssueKind> {
+        walk.issues.iter().map(|i| i.kind).collect()
+    }
+
+    #[test]
+    fn test_canonical_encoding() {
+        let row = chained(None, 1);
+        let canonical = row.canonical();
+
+        assert!(canonical.starts_with(&format!("64:{}|1:1|36:", GENESIS_HASH)));
+        // Length prefixes keep the separator inside a value unambiguous
+        assert!(canonical.contains("|3:a|b|"));
+        assert!(canonical.contains("|-|-|-|"));
+        assert!(canonical.ends_with("|27:2026-03-19T10:30:00.000000Z"));
+        assert_eq!(row.compute_hash().len(), 64);
+    }
+
+    #[test]
+    fn test_intact_chain_is_valid() {
+        let a = chained(None, 1);
+        let b = chained(Some(&a), 2);
+        let c = chained(Some(&b), 3);
+
+        let walk = walk(&[a, b, c.clone()], &c);
+        assert_eq!(walk.rows_checked, 3);
+        assert_eq!(walk.issue_count, 0);
+    }
+
+    #[test]
+    fn test_modified_row_detected() {
+        let a = chained(None, 1);
+        let mut b = chained(Some(&a), 2);
+        let c = chained(Some(&b), 3);
+        b.action = "READ".to_string();
+
+        assert_eq!(
+            kinds(&walk(&[a, b, c.clone()], &c)),
+            vec![AuditChainIssueKind::HashMismatch]
+        );
+    }
+
+    #[test]
+    fn test_deleted_rows_detected() {
+        let a = chained(None, 1);
+        let b = chained(Some(&a), 2);
+        let c = chained(Some(&b), 3);
+
+        // Deleted from the middle
+        assert_eq!(
+            kinds(&walk(&[a.clone(), c.clone()], &c)),
+            vec![AuditChainIssueKind::Gap]
+        );
+        // Deleted from the end
+        assert_eq!(
+            kinds(&walk(&[a, b], &c)),
+            vec![AuditChainIssueKind::HeadMismatch]
+        );
+    }
+
+    #[test]
+    fn test_rehashed_row_breaks_link() {
+        let a = chained(None, 1);
+        let mut b = chained(Some(&a), 2);
+        let c = chained(Some(&b), 3);
+
+        // Modified and re-hashed: b is self-consistent but c no longer links to it
+        b.action = "READ".to_string();
+        b.row_hash = b.compute_hash();
+
+        assert_eq!(
+            kinds(&walk(&[a, b, c.clone()], &c)),
+            vec![AuditChainIssueKind::BrokenLink]
+        );
+    }
+
+    #[test]
+    fn test_walk_after_pruned_partitions() {
+        let a = chained(None, 1);
+        let b = chained(Some(&a), 2);
+        let c = chained(Some(&b), 3);
+
+        // a and b dropped by retention, pruned position recorded
+        let pruned = ChainWalk::after_pruned(b.chain_seq, b.row_hash.clone());
+        let walk = walk_from(pruned, &[c.clone()], &c);
+        assert_eq!(walk.rows_checked, 1);
+        assert_eq!(walk.issue_count, 0);
+
+        // Without the pruned position the missing prefix is a gap
+        assert_eq!(
+            kinds(&walk_from(ChainWalk::default(), &[c.clone()], &c)),
+            vec![AuditChainIssueKind::Gap]
+        );
+    }
+}
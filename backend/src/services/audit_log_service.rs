@@ -9,18 +9,277 @@
  * - Rich filtering (by user, action, entity, date range)
  * - Statistics and activity summaries
  * - CSV/JSON export with rate limiting consideration
+ * - Streamed CSV export of every matching log (no row limit)
  * - User activity tracking
  */
 
-use chrono::{DateTime, Duration, Utc};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use futures::stream::{self, BoxStream, StreamExt, TryStreamExt};
 use sqlx::PgPool;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use uuid::Uuid;
 
+use crate::db::rls::begin_with_rls;
 use crate::models::audit_log::{
     ActionCount, AuditLogResponse, AuditLogStatistics, AuditLogsFilter,
-    EntityTypeCount, ExportAuditLogsRequest, ListAuditLogsResponse,
-    UserActivityCount, UserActivitySummary,
+    EntityTypeCount, ExportAuditLogsRequest, ListAuditLogsResponse, PatientAccessEntry,
+    PatientAccessLog, PatientAccessLogFilter, PatientAccessorSummary, RoleActivitySummary,
+    UserActivityCount, UserActivityReport, UserActivityReportRow, UserActivitySummary,
 };
+use crate::services::CsvRecord;
+
+/// Clinic timezone used for report day boundaries and working hours
+const CLINIC_TIMEZONE: &str = "Europe/Rome";
+
+/// Logs read per query by the streamed export
+const STREAM_PAGE_SIZE: i64 = 1000;
+
+/// Columns and filters of the streamed export, paged by (created_at, id)
+///
+/// Parameters: $1-$7 as in `export_logs`, $8/$9 the created_at and id of the
+/// last log of the previous page (NULL for the first page), $10 page size.
+const STREAM_EXPORT_SQL: &str = r#"
+    SELECT
+        al.id,
+        al.user_id,
+        al.action,
+        al.entity_type,
+        al.entity_id,
+        al.changes,
+        al.ip_address,
+        al.user_agent,
+        al.request_id,
+        al.created_at,
+        u.email as user_email
+    FROM audit_logs al
+    LEFT JOIN users u ON al.user_id = u.id
+    WHERE
+        ($1::uuid IS NULL OR al.user_id = $1)
+        AND ($2::text IS NULL OR al.action = $2)
+        AND ($3::text IS NULL OR al.entity_type = $3)
+        AND ($4::text IS NULL OR al.entity_id = $4)
+        AND ($5::date IS NULL OR al.created_at >= $5::date)
+        AND ($6::date IS NULL OR al.created_at < ($6::date + interval '1 day'))
+        AND ($7::text IS NULL OR al.ip_address::text LIKE '%' || $7 || '%')
+        AND ($8::timestamptz IS NULL OR (al.created_at, al.id) < ($8, $9))
+    ORDER BY al.created_at DESC, al.id DESC
+    LIMIT $10
+"#;
+
+/// Per-user, per-day activity derived from audit logs
+///
+/// Parameters: $1 first day, $2 last day (inclusive), $3 timezone.
+///
+/// The audit middleware and the handlers both log a request under the same
+/// request_id, so event counts are distinct requests. Patients are taken from
+/// PATIENT (handler) and `patients` (middleware) entries. A request is after
+/// hours when it falls outside the working hours for its date: a date
+/// override wins over the weekly schedule, and closed days count entirely.
+const DAILY_ACTIVITY_SQL: &str = r#"
+    WITH events AS (
+        SELECT
+            al.user_id,
+            (al.created_at AT TIME ZONE $3) AS local_ts,
+            (al.created_at AT TIME ZONE $3)::date AS activity_date,
+            COALESCE(al.request_id::text, al.id::text) AS request_key,
+            al.action,
+            al.entity_type,
+            al.entity_id,
+            al.changes
+        FROM audit_logs al
+        WHERE al.user_id IS NOT NULL
+          AND al.created_at >= ($1::date)::timestamp AT TIME ZONE $3
+          AND al.created_at < ($2::date + 1)::timestamp AT TIME ZONE $3
+    ),
+    flagged AS (
+        SELECT
+            e.*,
+            NOT COALESCE(
+                (
+                    SELECT CASE
+                        WHEN o.override_type = 'CLOSED' THEN false
+                        ELSE e.local_ts::time >= o.start_time AND e.local_ts::time < o.end_time
+                    END
+                    FROM working_hours_overrides o
+                    WHERE o.override_date = e.activity_date
+                    LIMIT 1
+                ),
+                (
+                    SELECT w.is_working_day
+                        AND e.local_ts::time >= w.start_time
+                        AND e.local_ts::time < w.end_time
+                    FROM default_working_hours w
+                    WHERE w.day_of_week = EXTRACT(ISODOW FROM e.activity_date)
+                    LIMIT 1
+                ),
+                false
+            ) AS after_hours,
+            (
+                e.entity_type IN ('PATIENT', 'patients')
+                AND e.entity_id ~* '^[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}$'
+            ) AS patient_access
+        FROM events e
+    )
+    SELECT
+        activity_date,
+        user_id,
+        COUNT(*) FILTER (WHERE action = 'LOGIN' AND entity_type = 'USER')::int AS logins,
+        COALESCE(
+            array_agg(DISTINCT CASE WHEN patient_access THEN entity_id::uuid END)
+                FILTER (WHERE patient_access),
+            '{}'
+        ) AS patient_ids,
+        COUNT(DISTINCT request_key) FILTER (WHERE patient_access)::int AS patient_access_events,
+        COUNT(*) FILTER (
+            WHERE action = 'CREATE'
+              AND entity_type = 'DOCUMENT'
+              AND changes->>'type' IN ('generated', 'print_bundle')
+        )::int AS documents_generated,
+        COUNT(DISTINCT request_key) FILTER (WHERE after_hours)::int AS after_hours_events,
+        COUNT(DISTINCT request_key)::int AS total_events
+    FROM flagged
+    GROUP BY activity_date, user_id
+"#;
+
+/// Accesses to one patient's chart, one row per request
+///
+/// Parameters: $1 patient ID, $2 first day, $3 last day (inclusive),
+/// $4 user ID, $5 timezone. Day and user filters are skipped when NULL.
+///
+/// Audit rows are matched on the patient itself and on every record that
+/// belongs to the patient (visits, diagnoses, prescriptions, appointments,
+/// documents, insurance, notifications), under both the handler entity types
+/// (VISIT) and the path segments logged by the middleware (visits). The
+/// middleware and handler rows of one request collapse into a single access
+/// with the strongest type: MODIFY, then EXPORT, then VIEW.
+const PATIENT_ACCESSES_SQL: &str = r#"
+    WITH related AS (
+        SELECT $1::text AS entity_id, 'PATIENT' AS record_type, ARRAY['PATIENT', 'patients'] AS entity_types
+        UNION ALL
+        SELECT id::text, 'PATIENT_INSURANCE', ARRAY['PATIENT_INSURANCE'] FROM patient_insurance WHERE patient_id = $1
+        UNION ALL
+        SELECT id::text, 'VISIT', ARRAY['VISIT', 'visits'] FROM visits WHERE patient_id = $1
+        UNION ALL
+        SELECT id::text, 'DIAGNOSIS', ARRAY['DIAGNOSIS', 'diagnoses'] FROM visit_diagnoses WHERE patient_id = $1
+        UNION ALL
+        SELECT id::text, 'PRESCRIPTION', ARRAY['PRESCRIPTION', 'prescriptions'] FROM prescriptions WHERE patient_id = $1
+        UNION ALL
+        SELECT id::text, 'APPOINTMENT', ARRAY['APPOINTMENT', 'appointments'] FROM appointments WHERE patient_id = $1
+        UNION ALL
+        SELECT id::text, 'DOCUMENT', ARRAY['DOCUMENT', 'documents'] FROM generated_documents WHERE patient_id = $1
+        UNION ALL
+        SELECT id::text, 'NOTIFICATION', ARRAY['NOTIFICATION', 'notifications'] FROM notification_queue WHERE patient_id = $1
+    ),
+    events AS (
+        SELECT
+            al.id,
+            al.user_id,
+            al.impersonator_id,
+            al.action,
+            al.created_at,
+            host(al.ip_address) AS ip_address,
+            al.request_id,
+            COALESCE(al.request_id::text, al.id::text) AS request_key,
+            r.record_type,
+            al.entity_id AS record_id,
+            CASE
+                WHEN al.action IN ('CREATE', 'UPDATE', 'DELETE')
+                  OR al.action ~ '^(POST|PUT|PATCH|DELETE) ' THEN 'MODIFY'
+                WHEN al.action = 'EXPORT' THEN 'EXPORT'
+                ELSE 'VIEW'
+            END AS access_type
+        FROM audit_logs al
+        JOIN related r ON al.entity_id = r.entity_id AND al.entity_type = ANY(r.entity_types)
+        WHERE al.user_id IS NOT NULL
+          AND ($2::date IS NULL OR al.created_at >= ($2::date)::timestamp AT TIME ZONE $5)
+          AND ($3::date IS NULL OR al.created_at < ($3::date + 1)::timestamp AT TIME ZONE $5)
+          AND ($4::uuid IS NULL OR al.user_id = $4)
+    ),
+    accesses AS (
+        SELECT DISTINCT ON (request_key)
+            request_key, user_id, impersonator_id, action, ip_address, request_id,
+            record_type, record_id, access_type,
+            MIN(created_at) OVER (PARTITION BY request_key) AS accessed_at
+        FROM events
+        ORDER BY
+            request_key,
+            CASE access_type WHEN 'MODIFY' THEN 0 WHEN 'EXPORT' THEN 1 ELSE 2 END,
+            created_at
+    )
+"#;
+
+/// One access to a patient's chart
+#[derive(Debug, sqlx::FromRow)]
+struct PatientAccessRow {
+    accessed_at: DateTime<Utc>,
+    user_id: Uuid,
+    username: Option<String>,
+    full_name: Option<String>,
+    role: Option<String>,
+    impersonator_id: Option<Uuid>,
+    access_type: String,
+    record_type: String,
+    record_id: Option<String>,
+    action: String,
+    ip_address: Option<String>,
+    request_id: Option<Uuid>,
+    total: i64,
+}
+
+/// Accesses to a patient's chart by one user
+#[derive(Debug, sqlx::FromRow)]
+struct PatientAccessorRow {
+    user_id: Uuid,
+    username: Option<String>,
+    full_name: Option<String>,
+    role: Option<String>,
+    views: i64,
+    modifications: i64,
+    exports: i64,
+    first_access: DateTime<Utc>,
+    last_access: DateTime<Utc>,
+}
+
+/// One user's activity on one day
+#[derive(Debug, Clone, sqlx::FromRow)]
+struct DailyActivityRow {
+    activity_date: NaiveDate,
+    user_id: Uuid,
+    logins: i32,
+    patient_ids: Vec<Uuid>,
+    patient_access_events: i32,
+    documents_generated: i32,
+    after_hours_events: i32,
+    total_events: i32,
+}
+
+/// Activity totals for one user across days
+#[derive(Debug, Default)]
+struct ActivityTotals {
+    logins: i64,
+    patient_ids: HashSet<Uuid>,
+    patient_access_events: i64,
+    documents_generated: i64,
+    after_hours_events: i64,
+    total_events: i64,
+    active_days: i64,
+}
+
+/// Sum daily rows per user (patients are counted once across the period)
+fn merge_daily_activity(days: &[DailyActivityRow]) -> HashMap<Uuid, ActivityTotals> {
+    let mut totals: HashMap<Uuid, ActivityTotals> = HashMap::new();
+    for day in days {
+        let entry = totals.entry(day.user_id).or_default();
+        entry.logins += i64::from(day.logins);
+        entry.patient_ids.extend(day.patient_ids.iter().copied());
+        entry.patient_access_events += i64::from(day.patient_access_events);
+        entry.documents_generated += i64::from(day.documents_generated);
+        entry.after_hours_events += i64::from(day.after_hours_events);
+        entry.total_events += i64::from(day.total_events);
+        entry.active_days += 1;
+    }
+    totals
+}
 
 /// Service for querying audit logs
 pub struct AuditLogService {
@@ -414,6 +673,288 @@ impl AuditLogService {
         }))
     }
 
+    /// Aggregate completed days in the range that have not been rolled up yet
+    ///
+    /// Audit logs are immutable, so a completed day only needs to be
+    /// aggregated once. Days are recorded in user_activity_rollup_days so
+    /// that days without any activity are not re-scanned.
+    async fn ensure_activity_rollups(
+        &self,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<(), sqlx::Error> {
+        let missing: Vec<NaiveDate> = sqlx::query_scalar(
+            r#"
+            SELECT d::date
+            FROM generate_series($1::date, $2::date, interval '1 day') d
+            WHERE NOT EXISTS (
+                SELECT 1 FROM user_activity_rollup_days r WHERE r.activity_date = d::date
+            )
+            ORDER BY 1
+            "#,
+        )
+        .bind(start)
+        .bind(end)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let (Some(first), Some(last)) = (missing.first().copied(), missing.last().copied()) else {
+            return Ok(());
+        };
+
+        tracing::info!("Aggregating user activity from {} to {}", first, last);
+
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(&format!(
+            r#"
+            INSERT INTO user_activity_daily (
+                activity_date, user_id, logins, patient_ids, patient_access_events,
+                documents_generated, after_hours_events, total_events
+            )
+            {}
+            ON CONFLICT (activity_date, user_id) DO UPDATE SET
+                logins = EXCLUDED.logins,
+                patient_ids = EXCLUDED.patient_ids,
+                patient_access_events = EXCLUDED.patient_access_events,
+                documents_generated = EXCLUDED.documents_generated,
+                after_hours_events = EXCLUDED.after_hours_events,
+                total_events = EXCLUDED.total_events,
+                aggregated_at = NOW()
+            "#,
+            DAILY_ACTIVITY_SQL
+        ))
+        .bind(first)
+        .bind(last)
+        .bind(CLINIC_TIMEZONE)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO user_activity_rollup_days (activity_date)
+            SELECT d::date FROM generate_series($1::date, $2::date, interval '1 day') d
+            ON CONFLICT (activity_date) DO NOTHING
+            "#,
+        )
+        .bind(first)
+        .bind(last)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Per-user activity report for access reviews
+    ///
+    /// Completed days come from the daily rollup (aggregated on first use);
+    /// today, if included, is computed live from the audit logs.
+    pub async fn get_user_activity_report(
+        &self,
+        start: NaiveDate,
+        end: NaiveDate,
+        today: NaiveDate,
+        role: Option<&str>,
+    ) -> Result<UserActivityReport, sqlx::Error> {
+        let mut days: Vec<DailyActivityRow> = Vec::new();
+
+        let rollup_end = end.min(today - Duration::days(1));
+        if start <= rollup_end {
+            self.ensure_activity_rollups(start, rollup_end).await?;
+
+            days = sqlx::query_as::<_, DailyActivityRow>(
+                r#"
+                SELECT
+                    activity_date, user_id, logins, patient_ids, patient_access_events,
+                    documents_generated, after_hours_events, total_events
+                FROM user_activity_daily
+                WHERE activity_date BETWEEN $1 AND $2
+                "#,
+            )
+            .bind(start)
+            .bind(rollup_end)
+            .fetch_all(&self.pool)
+            .await?;
+        }
+
+        if end >= today {
+            let live = sqlx::query_as::<_, DailyActivityRow>(DAILY_ACTIVITY_SQL)
+                .bind(today)
+                .bind(today)
+                .bind(CLINIC_TIMEZONE)
+                .fetch_all(&self.pool)
+                .await?;
+            days.extend(live);
+        }
+
+        let totals = merge_daily_activity(&days);
+        let user_ids: Vec<Uuid> = totals.keys().copied().collect();
+
+        let users: Vec<(Uuid, String, String, String, String)> = sqlx::query_as(
+            "SELECT id, username, first_name, last_name, role FROM users WHERE id = ANY($1)",
+        )
+        .bind(&user_ids)
+        .fetch_all(&self.pool)
+        .await?;
+        let users: HashMap<Uuid, (String, String, String)> = users
+            .into_iter()
+            .map(|(id, username, first_name, last_name, role)| {
+                (id, (username, format!("{} {}", first_name, last_name), role))
+            })
+            .collect();
+
+        let mut rows: Vec<UserActivityReportRow> = totals
+            .into_iter()
+            .map(|(user_id, t)| {
+                let user = users.get(&user_id);
+                UserActivityReportRow {
+                    user_id,
+                    username: user.map(|u| u.0.clone()),
+                    full_name: user.map(|u| u.1.clone()),
+                    role: user.map(|u| u.2.clone()),
+                    logins: t.logins,
+                    patients_accessed: t.patient_ids.len() as i64,
+                    patient_access_events: t.patient_access_events,
+                    documents_generated: t.documents_generated,
+                    after_hours_events: t.after_hours_events,
+                    total_events: t.total_events,
+                    active_days: t.active_days,
+                }
+            })
+            .filter(|row| match role {
+                Some(role) => row.role.as_deref().is_some_and(|r| r.eq_ignore_ascii_case(role)),
+                None => true,
+            })
+            .collect();
+        rows.sort_by(|a, b| a.username.cmp(&b.username));
+
+        let mut by_role: BTreeMap<String, RoleActivitySummary> = BTreeMap::new();
+        for row in &rows {
+            let role = row.role.clone().unwrap_or_else(|| "UNKNOWN".to_string());
+            let summary = by_role.entry(role.clone()).or_insert_with(|| RoleActivitySummary {
+                role,
+                users: 0,
+                logins: 0,
+                documents_generated: 0,
+                after_hours_events: 0,
+                total_events: 0,
+            });
+            summary.users += 1;
+            summary.logins += row.logins;
+            summary.documents_generated += row.documents_generated;
+            summary.after_hours_events += row.after_hours_events;
+            summary.total_events += row.total_events;
+        }
+
+        Ok(UserActivityReport {
+            start_date: start,
+            end_date: end,
+            generated_at: Utc::now(),
+            users: rows,
+            roles: by_role.into_values().collect(),
+        })
+    }
+
+    /// Who viewed or modified a patient's chart, and when
+    ///
+    /// Runs with the RLS context of `requested_by` because the related
+    /// clinical tables enforce row-level security.
+    pub async fn get_patient_access_log(
+        &self,
+        patient_id: Uuid,
+        mut filter: PatientAccessLogFilter,
+        requested_by: Uuid,
+    ) -> Result<PatientAccessLog, sqlx::Error> {
+        filter.validate();
+
+        let mut tx = begin_with_rls(&self.pool, requested_by).await?;
+
+        let accessors = sqlx::query_as::<_, PatientAccessorRow>(&format!(
+            r#"
+            {}
+            SELECT
+                a.user_id,
+                u.username,
+                u.first_name || ' ' || u.last_name AS full_name,
+                u.role::TEXT AS role,
+                COUNT(*) FILTER (WHERE a.access_type = 'VIEW') AS views,
+                COUNT(*) FILTER (WHERE a.access_type = 'MODIFY') AS modifications,
+                COUNT(*) FILTER (WHERE a.access_type = 'EXPORT') AS exports,
+                MIN(a.accessed_at) AS first_access,
+                MAX(a.accessed_at) AS last_access
+            FROM accesses a
+            LEFT JOIN users u ON u.id = a.user_id
+            GROUP BY a.user_id, u.username, u.first_name, u.last_name, u.role
+            ORDER BY MAX(a.accessed_at) DESC
+            "#,
+            PATIENT_ACCESSES_SQL
+        ))
+        .bind(patient_id)
+        .bind(filter.date_from)
+        .bind(filter.date_to)
+        .bind(filter.user_id)
+        .bind(CLINIC_TIMEZONE)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let rows = sqlx::query_as::<_, PatientAccessRow>(&format!(
+            r#"
+            {}
+            SELECT
+                a.accessed_at,
+                a.user_id,
+                u.username,
+                u.first_name || ' ' || u.last_name AS full_name,
+                u.role::TEXT AS role,
+                a.impersonator_id,
+                a.access_type,
+                a.record_type,
+                a.record_id,
+                a.action,
+                a.ip_address,
+                a.request_id,
+                COUNT(*) OVER () AS total
+            FROM accesses a
+            LEFT JOIN users u ON u.id = a.user_id
+            ORDER BY a.accessed_at DESC
+            LIMIT $6 OFFSET $7
+            "#,
+            PATIENT_ACCESSES_SQL
+        ))
+        .bind(patient_id)
+        .bind(filter.date_from)
+        .bind(filter.date_to)
+        .bind(filter.user_id)
+        .bind(CLINIC_TIMEZONE)
+        .bind(filter.page_size)
+        .bind(filter.offset())
+        .fetch_all(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        // Past the last page the window count is unavailable; fall back to the accessor totals
+        let total = rows.first().map(|r| r.total).unwrap_or_else(|| {
+            accessors
+                .iter()
+                .map(|a| a.views + a.modifications + a.exports)
+                .sum()
+        });
+        let total_pages = (total as f64 / filter.page_size as f64).ceil() as i64;
+
+        Ok(PatientAccessLog {
+            patient_id,
+            accessors: accessors.into_iter().map(Into::into).collect(),
+            entries: rows.into_iter().map(Into::into).collect(),
+            total,
+            page: filter.page,
+            page_size: filter.page_size,
+            total_pages,
+        })
+    }
+
     /// Export audit logs (returns data for export)
     ///
     /// This method returns data that can be formatted as CSV or JSON.
@@ -467,6 +1008,67 @@ impl AuditLogService {
         Ok(logs.into_iter().map(|l| l.into()).collect())
     }
 
+    /// Stream every log matching the filter, newest first
+    ///
+    /// Logs are read in pages of 1000 as the stream is consumed, keyed on
+    /// (created_at, id) so each page is an index range scan and no cursor is
+    /// held open between pages.
+    pub fn stream_logs(
+        &self,
+        mut filter: AuditLogsFilter,
+    ) -> BoxStream<'static, Result<AuditLogResponse, sqlx::Error>> {
+        filter.validate();
+        let pool = self.pool.clone();
+
+        // State: None once the last page was read, otherwise the position
+        // after which the next page starts (None: first page)
+        let pages = stream::try_unfold(
+            Some(None::<(DateTime<Utc>, i64)>),
+            move |state| {
+                let pool = pool.clone();
+                let filter = filter.clone();
+                async move {
+                    let Some(after) = state else {
+                        return Ok::<_, sqlx::Error>(None);
+                    };
+
+                    let logs = sqlx::query_as::<_, AuditLogWithEmail>(STREAM_EXPORT_SQL)
+                        .bind(filter.user_id)
+                        .bind(filter.action.as_deref())
+                        .bind(filter.entity_type.as_deref())
+                        .bind(filter.entity_id.as_deref())
+                        .bind(filter.date_from)
+                        .bind(filter.date_to)
+                        .bind(filter.ip_address.as_deref())
+                        .bind(after.map(|(created_at, _)| created_at))
+                        .bind(after.map(|(_, id)| id))
+                        .bind(STREAM_PAGE_SIZE)
+                        .fetch_all(&pool)
+                        .await?;
+
+                    if logs.is_empty() {
+                        return Ok(None);
+                    }
+
+                    let next = match logs.last() {
+                        Some(last) if logs.len() as i64 == STREAM_PAGE_SIZE => {
+                            Some(Some((last.created_at, last.id)))
+                        }
+                        _ => None,
+                    };
+                    let page: Vec<AuditLogResponse> = logs.into_iter().map(Into::into).collect();
+
+                    Ok(Some((page, next)))
+                }
+            },
+        );
+
+        pages
+            .map_ok(|page| stream::iter(page.into_iter().map(Ok::<_, sqlx::Error>)))
+            .try_flatten()
+            .boxed()
+    }
+
     /// Generate CSV string from audit logs
     pub fn generate_csv(logs: &[AuditLogResponse]) -> String {
         let mut csv = String::from("id,user_id,user_email,action,entity_type,entity_id,ip_address,user_agent,request_id,created_at\n");
@@ -504,6 +1106,38 @@ impl AuditLogService {
 // Internal DTOs for database queries
 // ============================================================================
 
+impl CsvRecord for AuditLogResponse {
+    fn csv_headers() -> &'static [&'static str] {
+        &[
+            "id",
+            "user_id",
+            "user_email",
+            "action",
+            "entity_type",
+            "entity_id",
+            "ip_address",
+            "user_agent",
+            "request_id",
+            "created_at",
+        ]
+    }
+
+    fn csv_fields(&self) -> Vec<String> {
+        vec![
+            self.id.to_string(),
+            self.user_id.map(|u| u.to_string()).unwrap_or_default(),
+            self.user_email.clone().unwrap_or_default(),
+            self.action.clone(),
+            self.entity_type.clone(),
+            self.entity_id.clone().unwrap_or_default(),
+            self.ip_address.clone().unwrap_or_default(),
+            self.user_agent.clone().unwrap_or_default(),
+            self.request_id.map(|r| r.to_string()).unwrap_or_default(),
+            self.created_at.to_rfc3339(),
+        ]
+    }
+}
+
 /// Internal struct for fetching audit logs with user email
 #[derive(Debug, sqlx::FromRow)]
 struct AuditLogWithEmail {
@@ -520,6 +1154,41 @@ struct AuditLogWithEmail {
     user_email: Option<String>,
 }
 
+impl From<PatientAccessRow> for PatientAccessEntry {
+    fn from(row: PatientAccessRow) -> Self {
+        Self {
+            accessed_at: row.accessed_at,
+            user_id: row.user_id,
+            username: row.username,
+            full_name: row.full_name,
+            role: row.role,
+            impersonator_id: row.impersonator_id,
+            access_type: row.access_type,
+            record_type: row.record_type,
+            record_id: row.record_id,
+            action: row.action,
+            ip_address: row.ip_address,
+            request_id: row.request_id,
+        }
+    }
+}
+
+impl From<PatientAccessorRow> for PatientAccessorSummary {
+    fn from(row: PatientAccessorRow) -> Self {
+        Self {
+            user_id: row.user_id,
+            username: row.username,
+            full_name: row.full_name,
+            role: row.role,
+            views: row.views,
+            modifications: row.modifications,
+            exports: row.exports,
+            first_access: row.first_access,
+            last_access: row.last_access,
+        }
+    }
+}
+
 impl From<AuditLogWithEmail> for AuditLogResponse {
     fn from(log: AuditLogWithEmail) -> Self {
         Self {
@@ -560,6 +1229,46 @@ impl From<UserActivityCountRow> for UserActivityCount {
 mod tests {
     use super::*;
 
+    fn day(user_id: Uuid, date: NaiveDate, patient_ids: Vec<Uuid>) -> DailyActivityRow {
+        DailyActivityRow {
+            activity_date: date,
+            user_id,
+            logins: 1,
+            patient_access_events: patient_ids.len() as i32,
+            patient_ids,
+            documents_generated: 2,
+            after_hours_events: 1,
+            total_events: 10,
+        }
+    }
+
+    #[test]
+    fn test_merge_daily_activity_counts_patients_once() {
+        let user = Uuid::new_v4();
+        let other = Uuid::new_v4();
+        let (p1, p2) = (Uuid::new_v4(), Uuid::new_v4());
+        let d1 = NaiveDate::from_ymd_opt(2026, 3, 2).unwrap();
+        let d2 = NaiveDate::from_ymd_opt(2026, 3, 3).unwrap();
+
+        let totals = merge_daily_activity(&[
+            day(user, d1, vec![p1, p2]),
+            day(user, d2, vec![p1]),
+            day(other, d1, vec![]),
+        ]);
+
+        let t = &totals[&user];
+        assert_eq!(t.logins, 2);
+        assert_eq!(t.patient_ids.len(), 2);
+        assert_eq!(t.patient_access_events, 3);
+        assert_eq!(t.documents_generated, 4);
+        assert_eq!(t.after_hours_events, 2);
+        assert_eq!(t.total_events, 20);
+        assert_eq!(t.active_days, 2);
+
+        assert_eq!(totals[&other].active_days, 1);
+        assert!(totals[&other].patient_ids.is_empty());
+    }
+
     #[test]
     fn test_csv_escape_simple() {
         assert_eq!(AuditLogService::escape_csv_field("simple"), "simple");
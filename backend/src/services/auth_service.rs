@@ -9,8 +9,10 @@ use sqlx::PgPool;
 use uuid::Uuid;
 
 use crate::config::{JwtConfig, SecurityConfig};
+use crate::models::trusted_device::TrustedDevice;
 use crate::models::{AuditAction, AuditLog, CreateAuditLog, EntityType, RequestContext, User, UserDto};
-use crate::services::{JwtService, TokenPair};
+use crate::services::siem_exporter::{self, SecurityEvent, SecurityEventKind};
+use crate::services::{CaptchaService, JwtService, TokenPair, TrustedDeviceService};
 use crate::utils::{AppError, PasswordHasherUtil, Result};
 
 /// Login request data
@@ -19,6 +21,14 @@ pub struct LoginRequest {
     pub username: String,
     pub password: String,
     pub mfa_code: Option<String>,
+    /// CAPTCHA token, required after repeated failed logins from the same IP
+    /// or against the same username
+    #[serde(default)]
+    pub captcha_token: Option<String>,
+    /// Trust this device for 30 days (skip MFA on later logins); only
+    /// honoured together with a valid `mfa_code`
+    #[serde(default)]
+    pub remember_device: bool,
 }
 
 /// Login response data
@@ -33,6 +43,12 @@ pub struct LoginResponse {
     /// Indicates if MFA setup is required (global mfa_required setting is ON but user hasn't set up MFA)
     #[serde(skip_serializing_if = "std::ops::Not::not")]
     pub requires_mfa_setup: bool,
+    /// Indicates the password has expired and must be changed before continuing
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub requires_password_change: bool,
+    /// Signed device token for the trusted device cookie (set by the handler)
+    #[serde(skip)]
+    pub trusted_device_token: Option<String>,
 }
 
 /// Authentication service
@@ -40,6 +56,7 @@ pub struct LoginResponse {
 pub struct AuthService {
     jwt_service: JwtService,
     security_config: SecurityConfig,
+    captcha: Option<CaptchaService>,
 }
 
 impl AuthService {
@@ -52,10 +69,16 @@ impl AuthService {
     pub fn new(jwt_config: JwtConfig, security_config: SecurityConfig) -> Self {
         Self {
             jwt_service: JwtService::new(jwt_config),
+            captcha: security_config.captcha.clone().map(CaptchaService::new),
             security_config,
         }
     }
 
+    /// CAPTCHA verification for login, if configured
+    pub fn captcha(&self) -> Option<&CaptchaService> {
+        self.captcha.as_ref()
+    }
+
     /// Generate JWT access and refresh tokens
     ///
     /// # Arguments
@@ -90,6 +113,7 @@ impl AuthService {
         &self,
         pool: &PgPool,
         login_req: LoginRequest,
+        device_token: Option<&str>,
         request_ctx: Option<&RequestContext>,
     ) -> Result<LoginResponse> {
         // Generic credential error message — identical for all failure modes
@@ -131,22 +155,59 @@ impl AuthService {
         // Verify password
         if !PasswordHasherUtil::verify_password(&login_req.password, &user.password_hash) {
             // Increment failed login attempts
-            user.increment_failed_login(
-                pool,
-                self.security_config.max_failed_login_attempts,
-                self.security_config.lockout_duration,
-            )
-            .await?;
+            let locked_until = user
+                .increment_failed_login(
+                    pool,
+                    self.security_config.max_failed_login_attempts,
+                    self.security_config.lockout_duration,
+                )
+                .await?;
+
+            if let Some(locked_until) = locked_until {
+                tracing::warn!("Account {} locked until {}", user.id, locked_until);
+                siem_exporter::emit(SecurityEvent {
+                    user_id: Some(user.id),
+                    username: Some(user.username.clone()),
+                    ip_address: request_ctx.and_then(|c| c.ip_address.clone()),
+                    request_id: request_ctx.map(|c| c.request_id),
+                    entity_type: Some(EntityType::User.to_string()),
+                    entity_id: Some(user.id.to_string()),
+                    ..SecurityEvent::new(
+                        SecurityEventKind::AccountLockout,
+                        "LOGIN",
+                        format!(
+                            "Account locked until {} after {} failed logins",
+                            locked_until.to_rfc3339(),
+                            self.security_config.max_failed_login_attempts
+                        ),
+                    )
+                });
+            }
 
             return Err(credential_error());
         }
 
-        // Check MFA if enabled
+        // Check MFA if enabled (not needed on a trusted device)
+        let mut trusted_device_id = None;
+        let mut trusted_device_token = None;
         if user.has_mfa_enabled() {
+            if let Some(token) = device_token {
+                trusted_device_id = self.use_trusted_device(pool, token, user.id).await?;
+            }
+        }
+
+        if let Some(device_id) = trusted_device_id {
+            tracing::info!("MFA skipped for user {} on trusted device {}", user.id, device_id);
+        } else if user.has_mfa_enabled() {
             match login_req.mfa_code {
                 Some(mfa_code) => {
                     // Try to verify MFA code (TOTP or backup code)
                     self.verify_mfa_code_or_backup(pool, &user, &mfa_code).await?;
+
+                    if login_req.remember_device {
+                        trusted_device_token =
+                            Some(self.trust_device(pool, user.id, request_ctx).await?);
+                    }
                 }
                 None => {
                     // MFA is required but no code provided - return requiresMfa flag
@@ -160,6 +221,8 @@ impl AuthService {
                         tokens,
                         requires_mfa: true,
                         requires_mfa_setup: false,
+                        requires_password_change: false,
+                        trusted_device_token: None,
                     });
                 }
             }
@@ -180,6 +243,8 @@ impl AuthService {
             tokens,
             requires_mfa: false,
             requires_mfa_setup: false,
+            requires_password_change: false,
+            trusted_device_token,
         };
 
         tracing::info!("User {} logged in successfully", login_req.username);
@@ -192,7 +257,9 @@ impl AuthService {
                 action: AuditAction::Login,
                 entity_type: EntityType::User,
                 entity_id: Some(user_id.to_string()),
-                changes: None,
+                changes: trusted_device_id.map(|device_id| {
+                    serde_json::json!({ "mfa": "trusted_device", "device_id": device_id })
+                }),
                 ip_address: request_ctx.and_then(|c| c.ip_address.clone()),
                 user_agent: request_ctx.and_then(|c| c.user_agent.clone()),
                 request_id: request_ctx.map(|c| c.request_id),
@@ -203,6 +270,78 @@ impl AuthService {
         Ok(response)
     }
 
+    /// Check a trusted device token for this user and mark the device used
+    ///
+    /// Invalid, expired, revoked or foreign tokens yield None so the login
+    /// falls back to the MFA code step.
+    async fn use_trusted_device(&self, pool: &PgPool, token: &str, user_id: Uuid) -> Result<Option<Uuid>> {
+        let Ok(claims) = self.jwt_service.validate_device_token(token) else {
+            return Ok(None);
+        };
+        if claims.sub != user_id.to_string() {
+            return Ok(None);
+        }
+        let Ok(device_id) = Uuid::parse_str(&claims.did) else {
+            return Ok(None);
+        };
+
+        let device = TrustedDeviceService::new(pool.clone())
+            .use_active(user_id, device_id)
+            .await?;
+
+        Ok(device.map(|d| d.id))
+    }
+
+    /// Trust the current device for 30 days and return its signed token
+    async fn trust_device(
+        &self,
+        pool: &PgPool,
+        user_id: Uuid,
+        request_ctx: Option<&RequestContext>,
+    ) -> Result<String> {
+        let expires_at = TrustedDevice::expiry_from(chrono::Utc::now());
+        let device = TrustedDeviceService::new(pool.clone())
+            .create(
+                user_id,
+                TrustedDevice::device_name_from_user_agent(
+                    request_ctx.and_then(|c| c.user_agent.as_deref()),
+                ),
+                request_ctx.and_then(|c| c.ip_address.as_deref()),
+                expires_at,
+            )
+            .await?;
+
+        let _ = AuditLog::create(
+            pool,
+            CreateAuditLog {
+                user_id: Some(user_id),
+                action: AuditAction::Create,
+                entity_type: EntityType::User,
+                entity_id: Some(user_id.to_string()),
+                changes: Some(serde_json::json!({
+                    "event": "trusted_device_added",
+                    "device_id": device.id,
+                    "expires_at": device.expires_at,
+                })),
+                ip_address: request_ctx.and_then(|c| c.ip_address.clone()),
+                user_agent: request_ctx.and_then(|c| c.user_agent.clone()),
+                request_id: request_ctx.map(|c| c.request_id),
+            },
+        )
+        .await;
+
+        self.jwt_service.generate_device_token(&user_id, &device.id, device.expires_at)
+    }
+
+    /// Device ID from a trusted device token issued to this user
+    pub fn trusted_device_id(&self, token: &str, user_id: Uuid) -> Option<Uuid> {
+        self.jwt_service
+            .validate_device_token(token)
+            .ok()
+            .filter(|claims| claims.sub == user_id.to_string())
+            .and_then(|claims| Uuid::parse_str(&claims.did).ok())
+    }
+
     /// Refresh access token using a valid refresh token
     ///
     /// # Arguments
@@ -370,6 +509,18 @@ impl AuthService {
         ))
     }
 
+    /// Issue an impersonation access token (see `JwtService::generate_impersonation_token`)
+    pub fn generate_impersonation_token(
+        &self,
+        user_id: &Uuid,
+        role: &crate::models::UserRole,
+        impersonator_id: &Uuid,
+        expires_at: chrono::DateTime<chrono::Utc>,
+    ) -> Result<(String, crate::services::Claims)> {
+        self.jwt_service
+            .generate_impersonation_token(user_id, role, impersonator_id, expires_at)
+    }
+
     /// Validate access token and extract claims
     ///
     /// # Arguments
@@ -383,6 +534,48 @@ impl AuthService {
         self.jwt_service.validate_access_token(token)
     }
 
+    /// Issue a patient portal token (see `JwtService::generate_portal_token`)
+    pub fn generate_portal_token(
+        &self,
+        patient_id: &Uuid,
+        session_id: &Uuid,
+        expires_at: chrono::DateTime<chrono::Utc>,
+    ) -> Result<String> {
+        self.jwt_service
+            .generate_portal_token(patient_id, session_id, expires_at)
+    }
+
+    /// Validate a patient portal token and extract claims
+    pub fn validate_portal_token(&self, token: &str) -> Result<crate::services::PortalClaims> {
+        self.jwt_service.validate_portal_token(token)
+    }
+
+    /// Generate an online booking slot token
+    pub fn generate_booking_slot_token(
+        &self,
+        provider_id: &Uuid,
+        start: chrono::DateTime<chrono::Utc>,
+        duration_minutes: i32,
+        appointment_type: &str,
+        expires_at: chrono::DateTime<chrono::Utc>,
+    ) -> Result<String> {
+        self.jwt_service.generate_booking_slot_token(
+            provider_id,
+            start,
+            duration_minutes,
+            appointment_type,
+            expires_at,
+        )
+    }
+
+    /// Validate an online booking slot token and extract claims
+    pub fn validate_booking_slot_token(
+        &self,
+        token: &str,
+    ) -> Result<crate::services::BookingSlotClaims> {
+        self.jwt_service.validate_booking_slot_token(token)
+    }
+
     /// Validate refresh token and extract claims
     ///
     /// # Arguments
@@ -395,6 +588,33 @@ impl AuthService {
     pub fn validate_refresh_token_only(&self, token: &str) -> Result<crate::services::Claims> {
         self.jwt_service.validate_refresh_token(token)
     }
+
+    /// Validate a token of either type and extract claims
+    ///
+    /// `token_type_hint` ("access_token" or "refresh_token") only decides which
+    /// type is tried first, as in RFC 7662.
+    ///
+    /// # Returns
+    ///
+    /// Claims if the token is a valid access or refresh token, `None` otherwise
+    pub fn validate_any_token(&self, token: &str, token_type_hint: Option<&str>) -> Option<crate::services::Claims> {
+        if token_type_hint == Some("refresh_token") {
+            self.jwt_service
+                .validate_refresh_token(token)
+                .or_else(|_| self.jwt_service.validate_access_token(token))
+                .ok()
+        } else {
+            self.jwt_service
+                .validate_access_token(token)
+                .or_else(|_| self.jwt_service.validate_refresh_token(token))
+                .ok()
+        }
+    }
+
+    /// Public keys for verifying access tokens (empty in HS256 mode)
+    pub fn jwks(&self) -> crate::utils::jwt_keys::JwkSet {
+        self.jwt_service.jwks()
+    }
 }
 
 #[cfg(test)]
@@ -409,6 +629,7 @@ mod tests {
             refresh_secret: "test_refresh_secret_key_minimum_32_characters_long".to_string(),
             access_token_expiry: 1800,
             refresh_token_expiry: 604800,
+            key_ring: None,
         };
 
         let security_config = SecurityConfig {
@@ -416,6 +637,7 @@ mod tests {
             session_timeout: 1800,
             max_failed_login_attempts: 5,
             lockout_duration: 900,
+            captcha: None,
         };
 
         (jwt_config, security_config)
@@ -440,4 +662,21 @@ mod tests {
         let claims = auth_service.validate_token(&tokens.access_token);
         assert!(claims.is_ok());
     }
+
+    #[test]
+    fn test_validate_any_token() {
+        let (jwt_config, security_config) = test_configs();
+        let auth_service = AuthService::new(jwt_config, security_config);
+
+        let user_id = Uuid::new_v4();
+        let tokens = auth_service.generate_tokens(&user_id, &UserRole::Doctor).unwrap();
+
+        let access = auth_service.validate_any_token(&tokens.access_token, Some("refresh_token"));
+        assert_eq!(access.unwrap().token_type, "access");
+
+        let refresh = auth_service.validate_any_token(&tokens.refresh_token, None);
+        assert_eq!(refresh.unwrap().token_type, "refresh");
+
+        assert!(auth_service.validate_any_token("not-a-token", None).is_none());
+    }
 }
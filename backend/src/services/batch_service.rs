@@ -0,0 +1,331 @@
+/*!
+ * Batch Service
+ *
+ * Runs the operations of a batch request in order, each through the same
+ * service method as the equivalent single request, and reports one result
+ * per operation.
+ *
+ * A transactional batch runs every operation in one database transaction;
+ * audit logs are written once it is committed.
+ */
+
+use axum::http::StatusCode;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::db::rls::begin_with_rls;
+use crate::models::{
+    Appointment, AuditAction, BatchOperation, BatchOperationResult, BatchRequest, BatchResponse,
+    RequestContext,
+};
+use crate::services::AppointmentService;
+use crate::utils::encryption::EncryptionKey;
+use crate::utils::{AppError, Result};
+
+/// Result of an operation that succeeded
+struct Applied {
+    appointment: Appointment,
+    action: AuditAction,
+    audit_changes: Option<serde_json::Value>,
+    status: StatusCode,
+}
+
+/// Batch service
+pub struct BatchService {
+    pool: PgPool,
+    appointments: AppointmentService,
+}
+
+impl BatchService {
+    /// Create a new batch service
+    pub fn new(pool: PgPool, encryption_key: EncryptionKey) -> Self {
+        Self {
+            appointments: AppointmentService::new(pool.clone(), encryption_key),
+            pool,
+        }
+    }
+
+    /// Run a batch of operations
+    pub async fn execute(
+        &self,
+        request: BatchRequest,
+        user_id: Uuid,
+        request_ctx: Option<&RequestContext>,
+    ) -> Result<BatchResponse> {
+        let results = if request.transactional {
+            self.execute_transactional(request.operations, user_id, request_ctx)
+                .await?
+        } else {
+            self.execute_independent(request.operations, user_id, request_ctx)
+                .await
+        };
+
+        Ok(BatchResponse::new(request.transactional, results))
+    }
+
+    /// Apply each operation on its own
+    async fn execute_independent(
+        &self,
+        operations: Vec<BatchOperation>,
+        user_id: Uuid,
+        request_ctx: Option<&RequestContext>,
+    ) -> Vec<BatchOperationResult> {
+        let mut results = Vec::with_capacity(operations.len());
+
+        for (index, operation) in operations.into_iter().enumerate() {
+            let outcome = async {
+                let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+                let applied = self.apply(&mut tx, operation, user_id).await?;
+
+                tx.commit().await?;
+                self.log(&applied, user_id, request_ctx).await;
+                Ok::<_, AppError>(applied)
+            }
+            .await;
+
+            results.push(match outcome {
+                Ok(applied) => self.success(index, applied),
+                Err(e) => failure(index, e),
+            });
+        }
+
+        results
+    }
+
+    /// Apply all operations or none
+    async fn execute_transactional(
+        &self,
+        operations: Vec<BatchOperation>,
+        user_id: Uuid,
+        request_ctx: Option<&RequestContext>,
+    ) -> Result<Vec<BatchOperationResult>> {
+        let count = operations.len();
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        let mut applied = Vec::with_capacity(count);
+        for (index, operation) in operations.into_iter().enumerate() {
+            match self.apply(&mut tx, operation, user_id).await {
+                Ok(done) => applied.push(done),
+                Err(e) => {
+                    // Dropping the transaction rolls back the operations before
+                    let mut results: Vec<_> = (0..index)
+                        .map(|i| {
+                            not_applied(
+                                i,
+                                "ROLLED_BACK",
+                                "Rolled back: another operation of the batch failed",
+                            )
+                        })
+                        .collect();
+                    results.push(failure(index, e));
+                    results.extend((index + 1..count).map(|i| {
+                        not_applied(
+                            i,
+                            "NOT_EXECUTED",
+                            "Not executed: an earlier operation of the batch failed",
+                        )
+                    }));
+                    return Ok(results);
+                }
+            }
+        }
+
+        tx.commit().await?;
+
+        let mut results = Vec::with_capacity(count);
+        for (index, done) in applied.into_iter().enumerate() {
+            self.log(&done, user_id, request_ctx).await;
+            results.push(self.success(index, done));
+        }
+
+        Ok(results)
+    }
+
+    /// Apply one operation in the given transaction
+    async fn apply(
+        &self,
+        tx: &mut PgConnection,
+        operation: BatchOperation,
+        user_id: Uuid,
+    ) -> Result<Applied> {
+        match operation {
+            BatchOperation::CreateAppointment { data } => {
+                let (appointment, changes) = self
+                    .appointments
+                    .create_appointment_in_tx(tx, data, user_id)
+                    .await
+                    .map_err(appointment_error)?;
+                Ok(Applied {
+                    appointment,
+                    action: AuditAction::Create,
+                    audit_changes: Some(changes),
+                    status: StatusCode::CREATED,
+                })
+            }
+            BatchOperation::UpdateAppointment { id, data } => {
+                let (appointment, changes) = self
+                    .appointments
+                    .update_appointment_in_tx(tx, id, data, user_id)
+                    .await
+                    .map_err(appointment_error)?;
+                Ok(Applied {
+                    appointment,
+                    action: AuditAction::Update,
+                    audit_changes: changes,
+                    status: StatusCode::OK,
+                })
+            }
+            BatchOperation::CancelAppointment { id, data } => {
+                data.validate()
+                    .map_err(|e| AppError::BadRequest(format!("Validation error: {}", e)))?;
+                let (appointment, changes) = self
+                    .appointments
+                    .cancel_appointment_in_tx(tx, id, data.cancellation_reason, user_id)
+                    .await
+                    .map_err(appointment_error)?;
+                Ok(Applied {
+                    appointment,
+                    action: AuditAction::Update,
+                    audit_changes: Some(changes),
+                    status: StatusCode::OK,
+                })
+            }
+        }
+    }
+
+    /// Audit a committed operation
+    async fn log(&self, applied: &Applied, user_id: Uuid, request_ctx: Option<&RequestContext>) {
+        if let Some(changes) = &applied.audit_changes {
+            self.appointments
+                .log_change(
+                    applied.action.clone(),
+                    applied.appointment.id,
+                    changes.clone(),
+                    user_id,
+                    request_ctx,
+                )
+                .await;
+        }
+    }
+
+    /// Result of a committed operation
+    fn success(&self, index: usize, applied: Applied) -> BatchOperationResult {
+        let body = self
+            .appointments
+            .decrypt(applied.appointment)
+            .map_err(|e| AppError::Internal(e.to_string()))
+            .and_then(|dto| {
+                serde_json::to_value(dto).map_err(|e| AppError::Internal(e.to_string()))
+            });
+
+        match body {
+            Ok(body) => BatchOperationResult {
+                index,
+                status: applied.status.as_u16(),
+                body,
+            },
+            Err(e) => failure(index, e),
+        }
+    }
+}
+
+/// Result of an operation that failed
+fn failure(index: usize, error: AppError) -> BatchOperationResult {
+    let (status, body) = error.status_and_body();
+    BatchOperationResult {
+        index,
+        status: status.as_u16(),
+        body,
+    }
+}
+
+/// Result of an operation of a failed transactional batch
+fn not_applied(index: usize, code: &str, message: &str) -> BatchOperationResult {
+    BatchOperationResult {
+        index,
+        status: StatusCode::FAILED_DEPENDENCY.as_u16(),
+        body: serde_json::json!({
+            "error": code,
+            "message": message,
+        }),
+    }
+}
+
+/// Map an appointment service error to the status of the single request
+fn appointment_error(e: anyhow::Error) -> AppError {
+    let e = match e.downcast::<AppError>() {
+        // Edited by someone else since the client read it, or the time is
+        // not available
+        Ok(app_error) => return app_error,
+        Err(e) => e,
+    };
+
+    let message = format!("{:#}", e);
+    let lower = message.to_lowercase();
+    if lower.contains("conflict") {
+        AppError::Conflict(message)
+    } else if lower.contains("not found") {
+        AppError::NotFound(message)
+    } else if lower.starts_with("invalid")
+        || lower.contains("cannot")
+        || lower.contains("working hours")
+        || lower.contains("overlaps")
+    {
+        AppError::BadRequest(message)
+    } else {
+        AppError::Internal(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    #[test]
+    fn test_appointment_error_status() {
+        let status = |e: anyhow::Error| appointment_error(e).status_and_body().0;
+
+        assert_eq!(
+            status(anyhow!(
+                "Scheduling conflict detected for provider at this time"
+            )),
+            StatusCode::CONFLICT
+        );
+        assert_eq!(
+            status(anyhow!("Appointment not found")),
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            status(anyhow!(
+                "Appointment with status Completed cannot be cancelled"
+            )),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            status(anyhow!("Cannot schedule appointment on a holiday (Natale)")),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            status(anyhow!("connection reset").context("Failed to update appointment")),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+        assert_eq!(
+            status(AppError::VersionConflict(serde_json::json!({})).into()),
+            StatusCode::CONFLICT
+        );
+    }
+
+    #[test]
+    fn test_failure_result() {
+        let result = failure(3, AppError::NotFound("Appointment not found".to_string()));
+        assert_eq!(result.index, 3);
+        assert_eq!(result.status, 404);
+        assert_eq!(result.body["error"], "NOT_FOUND");
+
+        let result = not_applied(0, "ROLLED_BACK", "Rolled back");
+        assert_eq!(result.status, 424);
+    }
+}
@@ -0,0 +1,692 @@
+/*!
+ * Online Booking Service
+ *
+ * Slots offered to the public and booking requests made from them.
+ *
+ * Slots come from the working hours (with overrides and holidays) of each
+ * bookable doctor, minus their busy times, within the booking window
+ * (minimum notice, horizon). Busy times are read through
+ * `booking_busy_intervals()`, which returns no patient data.
+ *
+ * A booking request keeps the patient's details encrypted until the link
+ * emailed to them is opened. Verification matches an existing record by
+ * email, date of birth and first name, or creates a new one, and adds a
+ * PENDING appointment; both run as the 'BOOKING' database role, which may do
+ * nothing else.
+ */
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    db::rls::apply_booking_context,
+    models::{
+        booking::{
+            generate_booking_verification_token, hash_booking_verification_token, BookableSlot,
+            BookingOutcome, BookingProvider, BookingRequest, BookingSettings, OfferedSlot,
+            BOOKING_ENABLED_SETTING_KEY, BOOKING_MAX_DAYS_SETTING_KEY,
+            BOOKING_MIN_NOTICE_SETTING_KEY, BOOKING_PROVIDERS_SETTING_KEY,
+            BOOKING_SLOT_TOKEN_MINUTES, BOOKING_TYPES_SETTING_KEY, BOOKING_VERIFICATION_MINUTES,
+            MAX_BOOKING_REQUESTS_PER_EMAIL,
+        },
+        patient_portal::PortalLoginCandidate,
+        AppointmentStatus, AppointmentType, BookingConfirmationResponse, CreateBookingRequest,
+        PatientBlindIndexes,
+    },
+    services::{
+        appointment_service::{day_time_slots, working_window},
+        email_service::EmailService,
+        AuthService, BookingSlotClaims, BrandingService, HolidayService, SettingsService,
+        WorkingHoursService,
+    },
+    utils::{encryption::EncryptionKey, html::escape_html, AppError, Result},
+};
+
+/// Frontend route that handles verification links
+const BOOKING_VERIFY_PATH: &str = "/booking/verify";
+
+/// Note on appointments created from online bookings
+const BOOKING_APPOINTMENT_NOTE: &str = "Booked online";
+
+const REQUEST_COLUMNS: &str = "id, provider_id, scheduled_start, duration_minutes, \
+    appointment_type, first_name, last_name, date_of_birth, email, phone, reason";
+
+/// Service for online booking
+pub struct BookingService {
+    pool: PgPool,
+    encryption_key: EncryptionKey,
+}
+
+impl BookingService {
+    /// Create a new online booking service
+    pub fn new(pool: PgPool, encryption_key: EncryptionKey) -> Self {
+        Self {
+            pool,
+            encryption_key,
+        }
+    }
+
+    /// Online booking settings (`booking.*`); defaults for missing or
+    /// invalid values
+    pub async fn settings(settings_service: &SettingsService) -> BookingSettings {
+        let defaults = BookingSettings::default();
+
+        BookingSettings {
+            enabled: settings_service
+                .get_setting_value::<bool>(BOOKING_ENABLED_SETTING_KEY)
+                .await
+                .ok()
+                .flatten()
+                .unwrap_or(defaults.enabled),
+            provider_ids: settings_service
+                .get_setting_value::<Vec<Uuid>>(BOOKING_PROVIDERS_SETTING_KEY)
+                .await
+                .ok()
+                .flatten()
+                .unwrap_or(defaults.provider_ids),
+            appointment_types: settings_service
+                .get_setting_value::<Vec<AppointmentType>>(BOOKING_TYPES_SETTING_KEY)
+                .await
+                .ok()
+                .flatten()
+                .unwrap_or(defaults.appointment_types),
+            min_notice_hours: settings_service
+                .get_setting_value::<i64>(BOOKING_MIN_NOTICE_SETTING_KEY)
+                .await
+                .ok()
+                .flatten()
+                .filter(|hours| *hours >= 0)
+                .unwrap_or(defaults.min_notice_hours),
+            max_days_ahead: settings_service
+                .get_setting_value::<i64>(BOOKING_MAX_DAYS_SETTING_KEY)
+                .await
+                .ok()
+                .flatten()
+                .filter(|days| *days >= 1)
+                .unwrap_or(defaults.max_days_ahead),
+        }
+    }
+
+    // =========================================================================
+    // Slots
+    // =========================================================================
+
+    /// Active doctors offered for online booking
+    pub async fn providers(&self, settings: &BookingSettings) -> Result<Vec<BookingProvider>> {
+        let providers = sqlx::query_as::<_, BookingProvider>(
+            r#"
+            SELECT id, first_name, last_name FROM users
+            WHERE role = 'DOCTOR' AND is_active = true
+              AND (cardinality($1::UUID[]) = 0 OR id = ANY($1))
+            ORDER BY last_name, first_name
+            "#,
+        )
+        .bind(&settings.provider_ids)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(providers)
+    }
+
+    /// Free slots of the given doctors from `from` for `days` days, each with
+    /// its slot token
+    ///
+    /// Days outside the booking window are skipped.
+    pub async fn available_slots(
+        &self,
+        auth_service: &AuthService,
+        settings: &BookingSettings,
+        providers: &[BookingProvider],
+        appointment_type: AppointmentType,
+        from: NaiveDate,
+        days: i64,
+    ) -> Result<Vec<BookableSlot>> {
+        let now = Utc::now();
+        let duration_minutes = appointment_type.default_duration();
+        let appointment_type_name = appointment_type_name(appointment_type);
+        let token_expires_at = now + Duration::minutes(BOOKING_SLOT_TOKEN_MINUTES);
+
+        let holiday_service = HolidayService::new(self.pool.clone());
+        let working_hours_service = WorkingHoursService::new(self.pool.clone());
+
+        let mut slots = Vec::new();
+        for date in from.iter_days().take(days.max(0) as usize) {
+            if date < now.date_naive()
+                || date > (now + Duration::days(settings.max_days_ahead)).date_naive()
+            {
+                continue;
+            }
+
+            let is_holiday = holiday_service
+                .is_holiday(date)
+                .await
+                .map_err(AppError::Internal)?;
+            if is_holiday {
+                continue;
+            }
+
+            let effective_hours = working_hours_service
+                .get_effective_hours_for_date(date)
+                .await
+                .map_err(AppError::Internal)?;
+            if !effective_hours.is_working_day {
+                continue;
+            }
+
+            let window = working_window(&effective_hours).map_err(internal)?;
+
+            for provider in providers {
+                let busy = self.busy_intervals(provider.id, window.0, window.1).await?;
+
+                for slot in day_time_slots(&effective_hours, window, &busy, duration_minutes) {
+                    if !slot.available
+                        || slot.end > window.1
+                        || !settings.allows_start(slot.start, now)
+                    {
+                        continue;
+                    }
+
+                    let slot_token = auth_service.generate_booking_slot_token(
+                        &provider.id,
+                        slot.start,
+                        duration_minutes,
+                        &appointment_type_name,
+                        token_expires_at,
+                    )?;
+
+                    slots.push(BookableSlot {
+                        provider_id: provider.id,
+                        provider_name: provider.display_name(),
+                        start: slot.start,
+                        end: slot.end,
+                        slot_token,
+                    });
+                }
+            }
+        }
+
+        slots.sort_by_key(|slot| slot.start);
+        Ok(slots)
+    }
+
+    /// Check that the slot of a slot token may still be booked
+    pub async fn offered_slot(
+        &self,
+        settings: &BookingSettings,
+        claims: &BookingSlotClaims,
+    ) -> Result<OfferedSlot> {
+        let unavailable = || {
+            AppError::Conflict(
+                "This slot is no longer available, please pick another one".to_string(),
+            )
+        };
+
+        let provider_id = Uuid::parse_str(&claims.sub)
+            .map_err(|_| AppError::BadRequest("Invalid slot".to_string()))?;
+        let start = DateTime::<Utc>::from_timestamp(claims.start, 0)
+            .ok_or_else(|| AppError::BadRequest("Invalid slot".to_string()))?;
+        let appointment_type: AppointmentType =
+            serde_json::from_value(serde_json::Value::String(claims.typ.clone()))
+                .map_err(|_| AppError::BadRequest("Invalid slot".to_string()))?;
+
+        if !settings.allows_type(appointment_type) || !settings.allows_start(start, Utc::now()) {
+            return Err(unavailable());
+        }
+
+        let provider = self
+            .providers(settings)
+            .await?
+            .into_iter()
+            .find(|p| p.id == provider_id)
+            .ok_or_else(unavailable)?;
+
+        let end = start + Duration::minutes(claims.dur as i64);
+        if !self
+            .busy_intervals(provider_id, start, end)
+            .await?
+            .is_empty()
+        {
+            return Err(unavailable());
+        }
+
+        Ok(OfferedSlot {
+            provider,
+            start,
+            duration_minutes: claims.dur,
+            appointment_type,
+        })
+    }
+
+    // =========================================================================
+    // Requests
+    // =========================================================================
+
+    /// Store a booking request and email its verification link
+    ///
+    /// Returns false, without storing anything, when the email address
+    /// already made `MAX_BOOKING_REQUESTS_PER_EMAIL` requests in the last
+    /// hour.
+    pub async fn create_request(
+        &self,
+        email_service: Option<&EmailService>,
+        slot: &OfferedSlot,
+        request: &CreateBookingRequest,
+        ip_address: Option<&str>,
+    ) -> Result<bool> {
+        let Some(email_service) = email_service.filter(|e| e.is_enabled()) else {
+            return Err(AppError::Internal(
+                "Online booking needs email to be configured".to_string(),
+            ));
+        };
+
+        let key = &self.encryption_key;
+        let email_bidx = PatientBlindIndexes::email_lookup(key, &request.email)
+            .ok_or_else(|| AppError::BadRequest("Invalid email address".to_string()))?;
+
+        let recent: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM booking_requests WHERE email_bidx = $1 AND created_at > $2",
+        )
+        .bind(&email_bidx)
+        .bind(Utc::now() - Duration::hours(1))
+        .fetch_one(&self.pool)
+        .await?;
+        if recent >= MAX_BOOKING_REQUESTS_PER_EMAIL {
+            tracing::warn!("Booking request not stored: too many recent requests for this email");
+            return Ok(false);
+        }
+
+        let token = generate_booking_verification_token();
+        let ip_address = ip_address.filter(|ip| ip.parse::<std::net::IpAddr>().is_ok());
+        let first_name = request.first_name.trim();
+
+        sqlx::query(
+            r#"
+            INSERT INTO booking_requests (
+                provider_id, scheduled_start, duration_minutes, appointment_type,
+                first_name, last_name, date_of_birth, email, phone, reason, email_bidx,
+                token_hash, expires_at, ip_address
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14::inet)
+            "#,
+        )
+        .bind(slot.provider.id)
+        .bind(slot.start)
+        .bind(slot.duration_minutes)
+        .bind(appointment_type_name(slot.appointment_type))
+        .bind(key.encrypt(first_name).map_err(internal)?)
+        .bind(key.encrypt(request.last_name.trim()).map_err(internal)?)
+        .bind(
+            key.encrypt(&request.date_of_birth.to_string())
+                .map_err(internal)?,
+        )
+        .bind(key.encrypt(request.email.trim()).map_err(internal)?)
+        .bind(key.encrypt_optional(&request.phone).map_err(internal)?)
+        .bind(key.encrypt_optional(&request.reason).map_err(internal)?)
+        .bind(&email_bidx)
+        .bind(hash_booking_verification_token(&token))
+        .bind(Utc::now() + Duration::minutes(BOOKING_VERIFICATION_MINUTES))
+        .bind(ip_address)
+        .execute(&self.pool)
+        .await?;
+
+        let base_url = BrandingService::new(self.pool.clone())
+            .load_settings()
+            .await
+            .ok()
+            .and_then(|s| s.public_base_url)
+            .unwrap_or_default();
+        let url = booking_verify_url(&base_url, &token);
+        let (subject, text, html) = booking_verification_email(first_name, &url);
+
+        match email_service
+            .send_notification(
+                request.email.trim(),
+                first_name,
+                &subject,
+                &text,
+                Some(&html),
+            )
+            .await
+        {
+            Ok(result) if result.success => Ok(true),
+            Ok(result) => Err(AppError::Internal(format!(
+                "Failed to send booking verification email: {}",
+                result.message
+            ))),
+            Err(e) => Err(AppError::Internal(format!(
+                "Failed to send booking verification email: {}",
+                e
+            ))),
+        }
+    }
+
+    /// Use a verification link: book the slot as a PENDING appointment
+    ///
+    /// The request is locked while it is used, so it books at most once.
+    /// When the slot was taken in the meantime the request is closed and
+    /// the patient has to pick another slot.
+    pub async fn verify_request(
+        &self,
+        token: &str,
+    ) -> Result<(BookingConfirmationResponse, BookingOutcome)> {
+        let key = &self.encryption_key;
+        let mut tx = self.pool.begin().await?;
+
+        let request = sqlx::query_as::<_, BookingRequest>(&format!(
+            r#"
+            SELECT {} FROM booking_requests
+            WHERE token_hash = $1 AND status = 'UNVERIFIED' AND expires_at > NOW()
+            FOR UPDATE
+            "#,
+            REQUEST_COLUMNS
+        ))
+        .bind(hash_booking_verification_token(token))
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| {
+            AppError::NotFound("Verification link is invalid or has expired".to_string())
+        })?;
+
+        let appointment_type: AppointmentType =
+            serde_json::from_value(serde_json::Value::String(request.appointment_type.clone()))
+                .map_err(|_| AppError::Internal("Invalid booking request".to_string()))?;
+        let scheduled_end =
+            request.scheduled_start + Duration::minutes(request.duration_minutes as i64);
+
+        let busy: Vec<(DateTime<Utc>, DateTime<Utc>)> = sqlx::query_as(
+            "SELECT scheduled_start, scheduled_end FROM booking_busy_intervals($1, $2, $3)",
+        )
+        .bind(request.provider_id)
+        .bind(request.scheduled_start)
+        .bind(scheduled_end)
+        .fetch_all(&mut *tx)
+        .await?;
+        if !busy.is_empty() || request.scheduled_start <= Utc::now() {
+            sqlx::query(
+                "UPDATE booking_requests SET status = 'UNAVAILABLE', verified_at = NOW() WHERE id = $1",
+            )
+            .bind(request.id)
+            .execute(&mut *tx)
+            .await?;
+            tx.commit().await?;
+
+            return Err(AppError::Conflict(
+                "This slot is no longer available, please book another one".to_string(),
+            ));
+        }
+
+        let first_name = self.decrypt(&request.first_name)?;
+        let last_name = self.decrypt(&request.last_name)?;
+        let date_of_birth: NaiveDate = self
+            .decrypt(&request.date_of_birth)?
+            .parse()
+            .map_err(|_| AppError::Internal("Invalid booking request".to_string()))?;
+        let email = self.decrypt(&request.email)?;
+        let phone = request
+            .phone
+            .as_deref()
+            .map(|p| self.decrypt(p))
+            .transpose()?;
+
+        apply_booking_context(&mut *tx).await?;
+
+        // Existing record: same email, date of birth and first name
+        let mut patient_id = None;
+        if let Some(email_bidx) = PatientBlindIndexes::email_lookup(key, &email) {
+            let candidates = sqlx::query_as::<_, PortalLoginCandidate>(
+                "SELECT patient_id, first_name, date_of_birth, email FROM portal_login_candidates($1)",
+            )
+            .bind(email_bidx)
+            .fetch_all(&mut *tx)
+            .await?;
+
+            for candidate in candidates {
+                let same_dob = self
+                    .decrypt(&candidate.date_of_birth)?
+                    .parse::<NaiveDate>()
+                    .ok()
+                    == Some(date_of_birth);
+                let same_name = self
+                    .decrypt(&candidate.first_name)?
+                    .trim()
+                    .eq_ignore_ascii_case(&first_name);
+                if same_dob && same_name {
+                    patient_id = Some(candidate.patient_id);
+                    break;
+                }
+            }
+        }
+
+        let new_patient = patient_id.is_none();
+        let patient_id = match patient_id {
+            Some(id) => id,
+            None => {
+                let id = Uuid::new_v4();
+                let blind_indexes = PatientBlindIndexes::compute(
+                    key,
+                    &first_name,
+                    &last_name,
+                    None,
+                    &[phone.as_deref()],
+                    Some(&email),
+                );
+
+                sqlx::query(
+                    r#"
+                    INSERT INTO patients (
+                        id, first_name, last_name, date_of_birth, gender,
+                        phone_primary, email, preferred_contact_method,
+                        last_name_bidx, last_name_prefix_bidx, phone_bidx, email_bidx,
+                        search_trigram_bidx
+                    )
+                    VALUES ($1, $2, $3, $4, 'UNKNOWN', $5, $6, 'EMAIL', $7, $8, $9, $10, $11)
+                    "#,
+                )
+                .bind(id)
+                .bind(key.encrypt(&first_name).map_err(internal)?)
+                .bind(key.encrypt(&last_name).map_err(internal)?)
+                .bind(key.encrypt(&date_of_birth.to_string()).map_err(internal)?)
+                .bind(key.encrypt_optional(&phone).map_err(internal)?)
+                .bind(key.encrypt(&email).map_err(internal)?)
+                .bind(blind_indexes.last_name)
+                .bind(blind_indexes.last_name_prefixes)
+                .bind(blind_indexes.phones)
+                .bind(blind_indexes.email)
+                .bind(blind_indexes.trigrams)
+                .execute(&mut *tx)
+                .await?;
+
+                id
+            }
+        };
+
+        // The exclusion constraint still refuses the slot if it was taken
+        // since the check above
+        let appointment_id = Uuid::new_v4();
+        sqlx::query(
+            r#"
+            INSERT INTO appointments (
+                id, patient_id, provider_id, scheduled_start, scheduled_end,
+                duration_minutes, type, reason, notes, status, text_encrypted
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, 'PENDING', true)
+            "#,
+        )
+        .bind(appointment_id)
+        .bind(patient_id)
+        .bind(request.provider_id)
+        .bind(request.scheduled_start)
+        .bind(scheduled_end)
+        .bind(request.duration_minutes)
+        .bind(appointment_type)
+        .bind(request.reason.as_deref())
+        .bind(key.encrypt(BOOKING_APPOINTMENT_NOTE).map_err(internal)?)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| match &e {
+            sqlx::Error::Database(db) if db.code().as_deref() == Some("23P01") => {
+                AppError::Conflict(
+                    "This slot is no longer available, please book another one".to_string(),
+                )
+            }
+            _ => AppError::Database(e),
+        })?;
+
+        sqlx::query(
+            r#"
+            UPDATE booking_requests
+            SET status = 'BOOKED', verified_at = NOW(), patient_id = $2, appointment_id = $3
+            WHERE id = $1
+            "#,
+        )
+        .bind(request.id)
+        .bind(patient_id)
+        .bind(appointment_id)
+        .execute(&mut *tx)
+        .await?;
+
+        let provider_name: Option<String> = sqlx::query_scalar(
+            "SELECT 'Dr. ' || first_name || ' ' || last_name FROM users WHERE id = $1",
+        )
+        .bind(request.provider_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok((
+            BookingConfirmationResponse {
+                status: AppointmentStatus::Pending,
+                scheduled_start: request.scheduled_start,
+                scheduled_end,
+                appointment_type,
+                provider_name: provider_name.unwrap_or_default(),
+                message:
+                    "Your booking request has been received. The practice will confirm it shortly."
+                        .to_string(),
+            },
+            BookingOutcome {
+                booking_request_id: request.id,
+                appointment_id,
+                patient_id,
+                new_patient,
+            },
+        ))
+    }
+
+    // =========================================================================
+    // Helpers
+    // =========================================================================
+
+    /// Busy times of a doctor overlapping `from`..`to`
+    async fn busy_intervals(
+        &self,
+        provider_id: Uuid,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<(DateTime<Utc>, DateTime<Utc>)>> {
+        let busy = sqlx::query_as(
+            "SELECT scheduled_start, scheduled_end FROM booking_busy_intervals($1, $2, $3)",
+        )
+        .bind(provider_id)
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(busy)
+    }
+
+    fn decrypt(&self, value: &str) -> Result<String> {
+        self.encryption_key
+            .decrypt(value)
+            .map_err(|e| AppError::Internal(format!("Failed to decrypt booking data: {}", e)))
+    }
+}
+
+fn internal(e: anyhow::Error) -> AppError {
+    AppError::Internal(e.to_string())
+}
+
+/// Wire name of an appointment type (e.g. "FOLLOW_UP")
+fn appointment_type_name(appointment_type: AppointmentType) -> String {
+    serde_json::to_value(appointment_type)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// Build the verification link for a token
+fn booking_verify_url(base_url: &str, token: &str) -> String {
+    format!(
+        "{}{}?token={}",
+        base_url.trim().trim_end_matches('/'),
+        BOOKING_VERIFY_PATH,
+        token
+    )
+}
+
+/// Build the verification email (subject, plain text, HTML)
+fn booking_verification_email(first_name: &str, url: &str) -> (String, String, String) {
+    let subject = "Confirm your appointment request".to_string();
+
+    let text = format!(
+        "Dear {first_name},\n\n\
+         Open the link below to send your appointment request to the practice:\n\n\
+         {url}\n\n\
+         The link expires in {minutes} minutes. The practice will confirm the appointment \
+         once it has received the request. If you did not request an appointment, you can \
+         ignore this email.\n\n\
+         Best regards,\nDocPat Medical Practice",
+        minutes = BOOKING_VERIFICATION_MINUTES,
+    );
+
+    let html = format!(
+        r#"<!DOCTYPE html>
+<html>
+<head><meta charset="utf-8"></head>
+<body style="font-family: Arial, sans-serif; line-height: 1.6; color: #333;">
+<div style="max-width: 600px; margin: 0 auto; padding: 20px;">
+<p>Dear {first_name},</p>
+<p>Open the link below to send your appointment request to the practice:</p>
+<p><a href="{url}">Confirm my request</a></p>
+<p style="font-size: 12px; color: #666;">The link expires in {minutes} minutes. The practice will confirm the appointment once it has received the request. If you did not request an appointment, you can ignore this email.</p>
+</div>
+</body>
+</html>"#,
+        first_name = escape_html(first_name),
+        url = escape_html(url),
+        minutes = BOOKING_VERIFICATION_MINUTES,
+    );
+
+    (subject, text, html)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_appointment_type_name() {
+        assert_eq!(
+            appointment_type_name(AppointmentType::FollowUp),
+            "FOLLOW_UP"
+        );
+        assert_eq!(
+            appointment_type_name(AppointmentType::NewPatient),
+            "NEW_PATIENT"
+        );
+    }
+
+    #[test]
+    fn test_booking_verification_email() {
+        let url = booking_verify_url("https://studio.example.com/", "abc");
+        assert_eq!(url, "https://studio.example.com/booking/verify?token=abc");
+
+        let (_, text, html) = booking_verification_email("<b>Anna</b>", &url);
+        assert!(text.contains("Dear <b>Anna</b>"));
+        assert!(html.contains("&lt;b&gt;Anna&lt;/b&gt;"));
+        assert!(html.contains("/booking/verify?token=abc"));
+    }
+}
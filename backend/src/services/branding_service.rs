@@ -0,0 +1,245 @@
+/*!
+ * Branding Service
+ *
+ * Resolves white-label theming metadata from the `branding` settings group and
+ * manages the associated image assets:
+ * - Public branding metadata for the frontend
+ * - Logo variant and email header uploads (stored via FileUploadService)
+ * - Branding values for the email and document renderers
+ *
+ * Setting writes go through SettingsService so they are validated, audited,
+ * and evicted from the settings cache.
+ */
+
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::branding::{
+    BrandingAsset, BrandingLogos, BrandingResponse, BrandingSettings, DOCUMENT_ACCENT_COLOR_KEY,
+    PRIMARY_COLOR_KEY, PUBLIC_BASE_URL_KEY,
+};
+use crate::models::system_setting::UpdateSettingRequest;
+use crate::models::uploaded_file::{FilePurpose, UploadedFile};
+use crate::models::RequestContext;
+use crate::services::email_service::EmailBranding;
+use crate::services::{FileUploadService, SettingsService};
+
+/// Service for practice branding metadata and assets
+pub struct BrandingService {
+    pool: PgPool,
+}
+
+impl BrandingService {
+    /// Create a new branding service
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    // =========================================================================
+    // Read Operations
+    // =========================================================================
+
+    /// Load raw branding settings (plus the clinic name) from system_settings
+    pub async fn load_settings(&self) -> Result<BrandingSettings> {
+        let rows: Vec<(String, serde_json::Value)> = sqlx::query_as(
+            r#"
+            SELECT setting_key, setting_value
+            FROM system_settings
+            WHERE setting_group = 'branding' OR setting_key = 'clinic.name'
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to fetch branding settings")?;
+
+        let mut settings = BrandingSettings::default();
+
+        for (key, value) in rows {
+            // Empty strings mean "not configured"
+            let value = match value {
+                serde_json::Value::String(s) if !s.trim().is_empty() => s.trim().to_string(),
+                _ => continue,
+            };
+
+            match key.as_str() {
+                "clinic.name" => settings.practice_name = Some(value),
+                PRIMARY_COLOR_KEY => settings.primary_color = Some(value),
+                DOCUMENT_ACCENT_COLOR_KEY => settings.document_accent_color = Some(value),
+                PUBLIC_BASE_URL_KEY => settings.public_base_url = Some(value),
+                other => {
+                    let asset = BrandingAsset::all()
+                        .into_iter()
+                        .find(|a| a.setting_key() == other);
+                    if let Some(asset) = asset {
+                        let file_id = Uuid::parse_str(&value).ok();
+                        match asset {
+                            BrandingAsset::LogoLight => settings.logo_light_file_id = file_id,
+                            BrandingAsset::LogoDark => settings.logo_dark_file_id = file_id,
+                            BrandingAsset::LogoCompact => settings.logo_compact_file_id = file_id,
+                            BrandingAsset::EmailHeader => settings.email_header_file_id = file_id,
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(settings)
+    }
+
+    /// Build the public branding metadata response
+    pub async fn get_branding(&self) -> Result<BrandingResponse> {
+        let settings = self.load_settings().await?;
+
+        let primary_logo = FileUploadService::get_logo(&self.pool)
+            .await?
+            .map(|_| "/api/v1/settings/logo/image".to_string());
+
+        let asset_url = |asset: BrandingAsset| {
+            settings.asset_file_id(asset).map(|_| asset.public_url())
+        };
+
+        Ok(BrandingResponse {
+            practice_name: settings
+                .practice_name
+                .clone()
+                .unwrap_or_else(|| "Studio Medico".to_string()),
+            primary_color: settings.primary_color(),
+            document_accent_color: settings.document_accent_color(),
+            logos: BrandingLogos {
+                primary: primary_logo,
+                light: asset_url(BrandingAsset::LogoLight),
+                dark: asset_url(BrandingAsset::LogoDark),
+                compact: asset_url(BrandingAsset::LogoCompact),
+            },
+            email_header_url: asset_url(BrandingAsset::EmailHeader),
+        })
+    }
+
+    /// Branding for outgoing HTML emails
+    ///
+    /// The header image is only included when `branding.public_base_url` is set,
+    /// since email clients need an absolute URL to fetch it.
+    pub async fn email_branding(&self) -> Result<EmailBranding> {
+        let settings = self.load_settings().await?;
+
+        Ok(EmailBranding {
+            primary_color: settings.primary_color(),
+            accent_color: settings.document_accent_color(),
+            header_image_url: settings.absolute_asset_url(BrandingAsset::EmailHeader),
+        })
+    }
+
+    /// Branding variables exposed to document templates as `clinic.branding`
+    pub async fn document_branding(&self) -> Result<serde_json::Value> {
+        let settings = self.load_settings().await?;
+        let logo_dark = self.get_asset_data_uri(BrandingAsset::LogoDark).await?;
+        let logo_compact = self.get_asset_data_uri(BrandingAsset::LogoCompact).await?;
+
+        Ok(serde_json::json!({
+            "primary_color": settings.primary_color(),
+            "accent_color": settings.document_accent_color(),
+            "logo_dark": logo_dark,
+            "logo_compact": logo_compact,
+        }))
+    }
+
+    /// Get the uploaded file and content for an asset slot
+    pub async fn get_asset(&self, asset: BrandingAsset) -> Result<Option<(UploadedFile, Vec<u8>)>> {
+        let settings = self.load_settings().await?;
+
+        match settings.asset_file_id(asset) {
+            Some(file_id) => FileUploadService::get_file_with_content(&self.pool, file_id).await,
+            None => Ok(None),
+        }
+    }
+
+    /// Get an asset as a base64 data URI (for embedding in rendered documents)
+    pub async fn get_asset_data_uri(&self, asset: BrandingAsset) -> Result<Option<String>> {
+        Ok(self.get_asset(asset).await?.map(|(file, content)| {
+            format!("data:{};base64,{}", file.mime_type, BASE64.encode(&content))
+        }))
+    }
+
+    // =========================================================================
+    // Write Operations
+    // =========================================================================
+
+    /// Upload an asset and point its setting at the new file
+    ///
+    /// The previously configured file (if any) is soft-deleted.
+    pub async fn upload_asset(
+        &self,
+        settings_service: &SettingsService,
+        asset: BrandingAsset,
+        content: &[u8],
+        original_filename: &str,
+        user_id: Uuid,
+        request_ctx: Option<&RequestContext>,
+    ) -> Result<UploadedFile> {
+        let previous_file_id = self.load_settings().await?.asset_file_id(asset);
+
+        let file = FileUploadService::upload_file(
+            &self.pool,
+            content,
+            original_filename,
+            FilePurpose::Branding,
+            Some(format!("Branding {}", asset)),
+            None,
+            user_id,
+        )
+        .await?;
+
+        settings_service
+            .update_setting(
+                asset.setting_key(),
+                UpdateSettingRequest {
+                    value: serde_json::Value::String(file.id.to_string()),
+                },
+                user_id,
+                request_ctx,
+            )
+            .await?;
+
+        if let Some(previous_id) = previous_file_id {
+            if let Err(e) = FileUploadService::delete_file_record(&self.pool, previous_id).await {
+                tracing::warn!("Failed to archive previous branding asset {}: {}", previous_id, e);
+            }
+        }
+
+        Ok(file)
+    }
+
+    /// Remove an asset (clears the setting and soft-deletes the file)
+    pub async fn remove_asset(
+        &self,
+        settings_service: &SettingsService,
+        asset: BrandingAsset,
+        user_id: Uuid,
+        request_ctx: Option<&RequestContext>,
+    ) -> Result<Uuid> {
+        let file_id = self
+            .load_settings()
+            .await?
+            .asset_file_id(asset)
+            .ok_or_else(|| anyhow!("Branding asset not found: {}", asset))?;
+
+        settings_service
+            .update_setting(
+                asset.setting_key(),
+                UpdateSettingRequest {
+                    value: serde_json::Value::String(String::new()),
+                },
+                user_id,
+                request_ctx,
+            )
+            .await?;
+
+        if let Err(e) = FileUploadService::delete_file_record(&self.pool, file_id).await {
+            tracing::warn!("Failed to archive branding asset {}: {}", file_id, e);
+        }
+
+        Ok(file_id)
+    }
+}
@@ -0,0 +1,221 @@
+/*!
+ * Calendar Feed Service
+ *
+ * Manages the iCalendar feed of each provider and renders it for calendar
+ * apps. Fetching the feed is authenticated by its token alone; the
+ * appointments are then read in a transaction acting as the provider, so
+ * RLS limits the feed to what the provider can see.
+ */
+
+use std::collections::HashMap;
+
+use crate::{
+    db::rls::begin_with_rls,
+    models::{
+        calendar_feed_path, generate_calendar_feed_token, hash_calendar_feed_token,
+        render_calendar, Appointment, CalendarEvent, CalendarFeed, CalendarPrivacyMode,
+        CreatedCalendarFeed, FEED_FUTURE_DAYS, FEED_PAST_DAYS,
+    },
+    utils::{encryption::EncryptionKey, AppError, Result},
+};
+use chrono::{Duration, Utc};
+use sqlx::PgPool;
+use tracing::info;
+use uuid::Uuid;
+
+const FEED_COLUMNS: &str =
+    "provider_id, token_hash, privacy_mode, last_accessed_at, created_at, updated_at";
+
+/// Calendar feed service
+pub struct CalendarFeedService {
+    pool: PgPool,
+    encryption_key: EncryptionKey,
+}
+
+impl CalendarFeedService {
+    /// Create a new calendar feed service
+    pub fn new(pool: PgPool, encryption_key: EncryptionKey) -> Self {
+        Self {
+            pool,
+            encryption_key,
+        }
+    }
+
+    /// The provider's feed, if they have one
+    pub async fn get_feed(&self, provider_id: Uuid) -> Result<Option<CalendarFeed>> {
+        let feed = sqlx::query_as::<_, CalendarFeed>(&format!(
+            "SELECT {} FROM calendar_feeds WHERE provider_id = $1",
+            FEED_COLUMNS
+        ))
+        .bind(provider_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(feed)
+    }
+
+    /// Create the provider's feed with a new token
+    ///
+    /// An existing feed gets the new token, so its old URL stops working.
+    pub async fn create_feed(
+        &self,
+        provider_id: Uuid,
+        privacy_mode: CalendarPrivacyMode,
+    ) -> Result<CreatedCalendarFeed> {
+        let token = generate_calendar_feed_token();
+
+        let feed = sqlx::query_as::<_, CalendarFeed>(&format!(
+            r#"
+            INSERT INTO calendar_feeds (provider_id, token_hash, privacy_mode)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (provider_id) DO UPDATE SET
+                token_hash = EXCLUDED.token_hash,
+                privacy_mode = EXCLUDED.privacy_mode,
+                last_accessed_at = NULL,
+                created_at = NOW(),
+                updated_at = NOW()
+            RETURNING {}
+            "#,
+            FEED_COLUMNS
+        ))
+        .bind(provider_id)
+        .bind(hash_calendar_feed_token(&token))
+        .bind(privacy_mode)
+        .fetch_one(&self.pool)
+        .await?;
+
+        info!("Calendar feed created for provider {}", provider_id);
+        Ok(CreatedCalendarFeed {
+            feed,
+            path: calendar_feed_path(&token),
+            token,
+        })
+    }
+
+    /// Change what the provider's feed shows, keeping its URL
+    pub async fn set_privacy_mode(
+        &self,
+        provider_id: Uuid,
+        privacy_mode: CalendarPrivacyMode,
+    ) -> Result<CalendarFeed> {
+        let feed = sqlx::query_as::<_, CalendarFeed>(&format!(
+            r#"
+            UPDATE calendar_feeds SET privacy_mode = $2, updated_at = NOW()
+            WHERE provider_id = $1
+            RETURNING {}
+            "#,
+            FEED_COLUMNS
+        ))
+        .bind(provider_id)
+        .bind(privacy_mode)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Calendar feed not found".to_string()))?;
+
+        Ok(feed)
+    }
+
+    /// Revoke the provider's feed
+    pub async fn delete_feed(&self, provider_id: Uuid) -> Result<()> {
+        let result = sqlx::query("DELETE FROM calendar_feeds WHERE provider_id = $1")
+            .bind(provider_id)
+            .execute(&self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound("Calendar feed not found".to_string()));
+        }
+
+        info!("Calendar feed revoked for provider {}", provider_id);
+        Ok(())
+    }
+
+    /// Render the feed with the given token
+    ///
+    /// `None` when no feed has the token or its provider is no longer active.
+    /// Covers the appointments of the last `FEED_PAST_DAYS` days and the next
+    /// `FEED_FUTURE_DAYS`, except cancelled ones and no-shows.
+    pub async fn render_feed(&self, token: &str) -> Result<Option<String>> {
+        let feed: Option<(Uuid, CalendarPrivacyMode, String)> = sqlx::query_as(
+            r#"
+            UPDATE calendar_feeds f SET last_accessed_at = NOW()
+            FROM users u
+            WHERE f.token_hash = $1 AND u.id = f.provider_id AND u.is_active = true
+            RETURNING f.provider_id, f.privacy_mode,
+                CASE WHEN u.role = 'DOCTOR' THEN 'Dr. ' ELSE '' END || u.first_name || ' ' || u.last_name
+            "#,
+        )
+        .bind(hash_calendar_feed_token(token))
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some((provider_id, privacy_mode, provider_name)) = feed else {
+            return Ok(None);
+        };
+
+        let now = Utc::now();
+        let mut tx = begin_with_rls(&self.pool, provider_id).await?;
+
+        let appointments = sqlx::query_as::<_, Appointment>(
+            r#"
+            SELECT * FROM appointments
+            WHERE provider_id = $1
+              AND scheduled_end > $2
+              AND scheduled_start < $3
+              AND status NOT IN ('CANCELLED', 'NO_SHOW')
+            ORDER BY scheduled_start
+            "#,
+        )
+        .bind(provider_id)
+        .bind(now - Duration::days(FEED_PAST_DAYS))
+        .bind(now + Duration::days(FEED_FUTURE_DAYS))
+        .fetch_all(&mut *tx)
+        .await?;
+
+        // Patient names only leave the database for FULL feeds
+        let mut patient_names = HashMap::new();
+        if privacy_mode == CalendarPrivacyMode::Full && !appointments.is_empty() {
+            let patient_ids: Vec<Uuid> = appointments.iter().map(|a| a.patient_id).collect();
+            let rows: Vec<(Uuid, String, String)> =
+                sqlx::query_as("SELECT id, first_name, last_name FROM patients WHERE id = ANY($1)")
+                    .bind(&patient_ids)
+                    .fetch_all(&mut *tx)
+                    .await?;
+
+            for (id, first_name, last_name) in rows {
+                let name = format!(
+                    "{} {}",
+                    self.decrypt(&first_name)?,
+                    self.decrypt(&last_name)?
+                );
+                patient_names.insert(id, name);
+            }
+        }
+
+        tx.commit().await?;
+
+        let events = appointments
+            .into_iter()
+            .map(|appointment| {
+                let appointment = appointment
+                    .decrypt(&self.encryption_key)
+                    .map_err(|e| AppError::Internal(e.to_string()))?;
+                Ok(CalendarEvent::from_appointment(
+                    &appointment,
+                    patient_names
+                        .get(&appointment.patient_id)
+                        .map(String::as_str),
+                    privacy_mode,
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Some(render_calendar(&provider_name, &events)))
+    }
+
+    fn decrypt(&self, value: &str) -> Result<String> {
+        self.encryption_key
+            .decrypt(value)
+            .map_err(|e| AppError::Internal(e.to_string()))
+    }
+}
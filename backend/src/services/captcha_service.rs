@@ -0,0 +1,141 @@
+/*!
+ * CAPTCHA Verification Service
+ *
+ * Verifies hCaptcha / Cloudflare Turnstile tokens submitted with login
+ * requests. Both providers share the same siteverify contract: a form POST
+ * with `secret`, `response` and optional `remoteip`, answered with
+ * `{"success": bool, "error-codes": [...]}`.
+ */
+
+use serde::Deserialize;
+use std::time::Duration;
+
+use crate::config::{CaptchaConfig, CaptchaProvider};
+
+/// Timeout for siteverify requests
+const VERIFY_TIMEOUT_SECS: u64 = 5;
+
+/// Siteverify response (fields common to hCaptcha and Turnstile)
+#[derive(Debug, Deserialize)]
+struct SiteVerifyResponse {
+    success: bool,
+    #[serde(rename = "error-codes", default)]
+    error_codes: Vec<String>,
+}
+
+/// CAPTCHA verification service
+#[derive(Clone)]
+pub struct CaptchaService {
+    config: CaptchaConfig,
+    client: reqwest::Client,
+}
+
+impl CaptchaService {
+    /// Create a new CAPTCHA service
+    pub fn new(config: CaptchaConfig) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(VERIFY_TIMEOUT_SECS))
+            .build()
+            .unwrap_or_default();
+
+        Self { config, client }
+    }
+
+    /// CAPTCHA provider
+    pub fn provider(&self) -> CaptchaProvider {
+        self.config.provider
+    }
+
+    /// Public site key for the frontend widget
+    pub fn site_key(&self) -> &str {
+        &self.config.site_key
+    }
+
+    /// Whether a client with this many recent failed logins must solve a CAPTCHA
+    pub fn is_required(&self, recent_failures: usize) -> bool {
+        recent_failures >= self.config.failure_threshold
+    }
+
+    /// Verify a CAPTCHA token with the provider
+    ///
+    /// Fails closed: provider or network errors count as a failed challenge.
+    pub async fn verify(&self, token: &str, remote_ip: Option<&str>) -> bool {
+        let mut form = vec![
+            ("secret", self.config.secret_key.as_str()),
+            ("response", token),
+        ];
+        if let Some(ip) = remote_ip {
+            form.push(("remoteip", ip));
+        }
+
+        let response = match self
+            .client
+            .post(self.config.provider.verify_url())
+            .form(&form)
+            .send()
+            .await
+        {
+            Ok(response) => response,
+            Err(e) => {
+                tracing::error!("CAPTCHA verification request failed: {}", e);
+                return false;
+            }
+        };
+
+        match response.json::<SiteVerifyResponse>().await {
+            Ok(result) if result.success => true,
+            Ok(result) => {
+                tracing::warn!("CAPTCHA verification rejected: {:?}", result.error_codes);
+                false
+            }
+            Err(e) => {
+                tracing::error!("Invalid CAPTCHA verification response: {}", e);
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service(threshold: usize) -> CaptchaService {
+        CaptchaService::new(CaptchaConfig {
+            provider: CaptchaProvider::Turnstile,
+            site_key: "site".to_string(),
+            secret_key: "secret".to_string(),
+            failure_threshold: threshold,
+        })
+    }
+
+    #[test]
+    fn test_is_required_after_threshold() {
+        let captcha = service(3);
+        assert!(!captcha.is_required(0));
+        assert!(!captcha.is_required(2));
+        assert!(captcha.is_required(3));
+        assert!(captcha.is_required(10));
+    }
+
+    #[test]
+    fn test_provider_from_name() {
+        assert_eq!(CaptchaProvider::from_name("hCaptcha"), Some(CaptchaProvider::HCaptcha));
+        assert_eq!(CaptchaProvider::from_name(" turnstile "), Some(CaptchaProvider::Turnstile));
+        assert_eq!(CaptchaProvider::from_name("recaptcha"), None);
+    }
+
+    #[test]
+    fn test_site_verify_response_parsing() {
+        let ok: SiteVerifyResponse = serde_json::from_str(r#"{"success": true}"#).unwrap();
+        assert!(ok.success);
+        assert!(ok.error_codes.is_empty());
+
+        let failed: SiteVerifyResponse = serde_json::from_str(
+            r#"{"success": false, "error-codes": ["invalid-input-response"], "hostname": "x"}"#,
+        )
+        .unwrap();
+        assert!(!failed.success);
+        assert_eq!(failed.error_codes, vec!["invalid-input-response"]);
+    }
+}
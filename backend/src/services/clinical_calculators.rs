@@ -0,0 +1,704 @@
+/*!
+ * Clinical Calculators
+ *
+ * Scores computed from structured inputs:
+ * - BMI with the WHO adult category
+ * - eGFR with the race-free CKD-EPI 2021 creatinine equation and the KDIGO
+ *   GFR category
+ * - CHA₂DS₂-VASc stroke risk in atrial fibrillation
+ * - HAS-BLED bleeding risk on anticoagulation
+ * - SCORE2 10-year cardiovascular risk (ESC 2021), calibrated to the
+ *   European risk region (Italy is a moderate risk region)
+ *
+ * The functions are pure; `VisitCalculationService` saves results into a
+ * visit.
+ */
+
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::utils::{AppError, Result};
+
+/// mg/dL to µmol/L for creatinine
+const CREATININE_UMOL_PER_MG_DL: f64 = 88.42;
+/// mg/dL to mmol/L for cholesterol
+const CHOLESTEROL_MG_DL_PER_MMOL: f64 = 38.67;
+
+/// Available calculators
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Calculator {
+    #[serde(rename = "BMI")]
+    Bmi,
+    #[serde(rename = "EGFR")]
+    Egfr,
+    #[serde(rename = "CHA2DS2_VASC")]
+    Cha2ds2Vasc,
+    #[serde(rename = "HAS_BLED")]
+    HasBled,
+    #[serde(rename = "SCORE2")]
+    Score2,
+}
+
+impl Calculator {
+    /// Convert to database string representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Calculator::Bmi => "BMI",
+            Calculator::Egfr => "EGFR",
+            Calculator::Cha2ds2Vasc => "CHA2DS2_VASC",
+            Calculator::HasBled => "HAS_BLED",
+            Calculator::Score2 => "SCORE2",
+        }
+    }
+}
+
+/// Sex as used by the equations
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Sex {
+    Male,
+    Female,
+}
+
+/// Unit of a creatinine value
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CreatinineUnit {
+    #[default]
+    #[serde(rename = "MG_DL")]
+    MgDl,
+    #[serde(rename = "UMOL_L")]
+    UmolL,
+}
+
+/// Unit of cholesterol values
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CholesterolUnit {
+    #[default]
+    #[serde(rename = "MG_DL")]
+    MgDl,
+    #[serde(rename = "MMOL_L")]
+    MmolL,
+}
+
+/// SCORE2 risk region, by national cardiovascular mortality
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RiskRegion {
+    Low,
+    /// Italy
+    #[default]
+    Moderate,
+    High,
+    VeryHigh,
+}
+
+/// Scored result of a calculator
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CalculatorResult {
+    pub calculator: Calculator,
+    pub score: f64,
+    pub unit: String,
+    /// Machine-readable category (e.g. `OVERWEIGHT`, `G3A`, `HIGH`)
+    pub category: String,
+    pub interpretation: String,
+    /// Criteria that contributed to a point score
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub factors: Vec<String>,
+}
+
+/// BMI input
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct BmiInput {
+    #[validate(range(min = 0.5, max = 500.0))]
+    pub weight_kg: f64,
+    #[validate(range(min = 20.0, max = 300.0))]
+    pub height_cm: f64,
+}
+
+/// eGFR input (adults)
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct EgfrInput {
+    #[validate(range(min = 18, max = 120, message = "CKD-EPI applies to adults (18-120)"))]
+    pub age: u32,
+    pub sex: Sex,
+    #[validate(range(min = 0.01, max = 2000.0))]
+    pub creatinine: f64,
+    #[serde(default)]
+    pub creatinine_unit: CreatinineUnit,
+}
+
+/// CHA₂DS₂-VASc input
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct Cha2ds2VascInput {
+    #[validate(range(min = 18, max = 120))]
+    pub age: u32,
+    pub sex: Sex,
+    #[serde(default)]
+    pub congestive_heart_failure: bool,
+    #[serde(default)]
+    pub hypertension: bool,
+    #[serde(default)]
+    pub diabetes: bool,
+    /// Prior stroke, TIA or thromboembolism
+    #[serde(default)]
+    pub stroke_tia_thromboembolism: bool,
+    /// Prior myocardial infarction, peripheral artery disease or aortic plaque
+    #[serde(default)]
+    pub vascular_disease: bool,
+}
+
+/// HAS-BLED input
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct HasBledInput {
+    #[validate(range(min = 18, max = 120))]
+    pub age: u32,
+    /// Uncontrolled, systolic above 160 mmHg
+    #[serde(default)]
+    pub hypertension: bool,
+    /// Dialysis, transplant or creatinine of 2.26 mg/dL or more
+    #[serde(default)]
+    pub abnormal_renal_function: bool,
+    /// Cirrhosis, or bilirubin above 2x / transaminases above 3x normal
+    #[serde(default)]
+    pub abnormal_liver_function: bool,
+    #[serde(default)]
+    pub stroke: bool,
+    /// Prior major bleeding or predisposition to bleeding
+    #[serde(default)]
+    pub bleeding_history: bool,
+    /// Time in therapeutic range below 60%
+    #[serde(default)]
+    pub labile_inr: bool,
+    /// Antiplatelet agents or NSAIDs
+    #[serde(default)]
+    pub antiplatelet_or_nsaid: bool,
+    /// 8 or more drinks a week
+    #[serde(default)]
+    pub alcohol: bool,
+}
+
+/// SCORE2 input (40-69 years, without established cardiovascular disease,
+/// diabetes or chronic kidney disease)
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct Score2Input {
+    #[validate(range(min = 40, max = 69, message = "SCORE2 applies from 40 to 69 years"))]
+    pub age: u32,
+    pub sex: Sex,
+    #[serde(default)]
+    pub smoker: bool,
+    #[validate(range(min = 80.0, max = 250.0))]
+    pub systolic_bp: f64,
+    #[validate(range(min = 1.0, max = 600.0))]
+    pub total_cholesterol: f64,
+    #[validate(range(min = 0.1, max = 200.0))]
+    pub hdl_cholesterol: f64,
+    #[serde(default)]
+    pub cholesterol_unit: CholesterolUnit,
+    #[serde(default)]
+    pub region: RiskRegion,
+}
+
+/// Inputs of any calculator, tagged with its name
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "calculator", content = "inputs")]
+pub enum CalculatorInput {
+    #[serde(rename = "BMI")]
+    Bmi(BmiInput),
+    #[serde(rename = "EGFR")]
+    Egfr(EgfrInput),
+    #[serde(rename = "CHA2DS2_VASC")]
+    Cha2ds2Vasc(Cha2ds2VascInput),
+    #[serde(rename = "HAS_BLED")]
+    HasBled(HasBledInput),
+    #[serde(rename = "SCORE2")]
+    Score2(Score2Input),
+}
+
+impl CalculatorInput {
+    pub fn calculator(&self) -> Calculator {
+        match self {
+            CalculatorInput::Bmi(_) => Calculator::Bmi,
+            CalculatorInput::Egfr(_) => Calculator::Egfr,
+            CalculatorInput::Cha2ds2Vasc(_) => Calculator::Cha2ds2Vasc,
+            CalculatorInput::HasBled(_) => Calculator::HasBled,
+            CalculatorInput::Score2(_) => Calculator::Score2,
+        }
+    }
+
+    /// Validate the inputs and compute the score
+    pub fn calculate(&self) -> Result<CalculatorResult> {
+        match self {
+            CalculatorInput::Bmi(input) => calculate_bmi(input),
+            CalculatorInput::Egfr(input) => calculate_egfr(input),
+            CalculatorInput::Cha2ds2Vasc(input) => calculate_cha2ds2_vasc(input),
+            CalculatorInput::HasBled(input) => calculate_has_bled(input),
+            CalculatorInput::Score2(input) => calculate_score2(input),
+        }
+    }
+}
+
+fn validate(input: &impl Validate) -> Result<()> {
+    input
+        .validate()
+        .map_err(|e| AppError::Validation(format!("Validation error: {}", e)))
+}
+
+fn round_to(value: f64, decimals: i32) -> f64 {
+    let factor = 10f64.powi(decimals);
+    (value * factor).round() / factor
+}
+
+/// Body mass index
+pub fn calculate_bmi(input: &BmiInput) -> Result<CalculatorResult> {
+    validate(input)?;
+
+    let height_m = input.height_cm / 100.0;
+    let bmi = round_to(input.weight_kg / (height_m * height_m), 1);
+    let (category, interpretation) = match bmi {
+        b if b < 18.5 => ("UNDERWEIGHT", "Underweight"),
+        b if b < 25.0 => ("NORMAL", "Normal weight"),
+        b if b < 30.0 => ("OVERWEIGHT", "Overweight"),
+        b if b < 35.0 => ("OBESITY_I", "Obesity class I"),
+        b if b < 40.0 => ("OBESITY_II", "Obesity class II"),
+        _ => ("OBESITY_III", "Obesity class III"),
+    };
+
+    Ok(CalculatorResult {
+        calculator: Calculator::Bmi,
+        score: bmi,
+        unit: "kg/m²".to_string(),
+        category: category.to_string(),
+        interpretation: interpretation.to_string(),
+        factors: Vec::new(),
+    })
+}
+
+/// Estimated GFR, CKD-EPI 2021 creatinine equation (without race)
+pub fn calculate_egfr(input: &EgfrInput) -> Result<CalculatorResult> {
+    validate(input)?;
+
+    let creatinine = match input.creatinine_unit {
+        CreatinineUnit::MgDl => input.creatinine,
+        CreatinineUnit::UmolL => input.creatinine / CREATININE_UMOL_PER_MG_DL,
+    };
+    if !(0.1..=20.0).contains(&creatinine) {
+        return Err(AppError::Validation(
+            "Creatinine must be between 0.1 and 20 mg/dL (8.8-1768 µmol/L)".to_string(),
+        ));
+    }
+
+    let (kappa, alpha, sex_factor) = match input.sex {
+        Sex::Female => (0.7, -0.241, 1.012),
+        Sex::Male => (0.9, -0.302, 1.0),
+    };
+    let ratio = creatinine / kappa;
+    let egfr = 142.0
+        * ratio.min(1.0).powf(alpha)
+        * ratio.max(1.0).powf(-1.200)
+        * 0.9938f64.powi(input.age as i32)
+        * sex_factor;
+    let egfr = egfr.round();
+
+    let (category, interpretation) = match egfr {
+        e if e >= 90.0 => ("G1", "Normal or high (G1)"),
+        e if e >= 60.0 => ("G2", "Mildly decreased (G2)"),
+        e if e >= 45.0 => ("G3A", "Mildly to moderately decreased (G3a)"),
+        e if e >= 30.0 => ("G3B", "Moderately to severely decreased (G3b)"),
+        e if e >= 15.0 => ("G4", "Severely decreased (G4)"),
+        _ => ("G5", "Kidney failure (G5)"),
+    };
+
+    Ok(CalculatorResult {
+        calculator: Calculator::Egfr,
+        score: egfr,
+        unit: "mL/min/1.73m²".to_string(),
+        category: category.to_string(),
+        interpretation: interpretation.to_string(),
+        factors: Vec::new(),
+    })
+}
+
+/// Adjusted annual stroke rate (%) by CHA₂DS₂-VASc score
+const CHA2DS2_VASC_STROKE_RATE: [f64; 10] = [0.0, 1.3, 2.2, 3.2, 4.0, 6.7, 9.8, 9.6, 6.7, 15.2];
+
+/// CHA₂DS₂-VASc stroke risk score
+pub fn calculate_cha2ds2_vasc(input: &Cha2ds2VascInput) -> Result<CalculatorResult> {
+    validate(input)?;
+
+    let mut score = 0;
+    let mut factors = Vec::new();
+    let mut add = |points: u32, factor: &str| {
+        score += points;
+        factors.push(factor.to_string());
+    };
+
+    if input.congestive_heart_failure {
+        add(1, "Congestive heart failure");
+    }
+    if input.hypertension {
+        add(1, "Hypertension");
+    }
+    if input.age >= 75 {
+        add(2, "Age 75 or over");
+    } else if input.age >= 65 {
+        add(1, "Age 65-74");
+    }
+    if input.diabetes {
+        add(1, "Diabetes");
+    }
+    if input.stroke_tia_thromboembolism {
+        add(2, "Prior stroke, TIA or thromboembolism");
+    }
+    if input.vascular_disease {
+        add(1, "Vascular disease");
+    }
+    if input.sex == Sex::Female {
+        add(1, "Female sex");
+    }
+
+    // Female sex alone does not raise the risk
+    let risk_factors = match input.sex {
+        Sex::Female => score - 1,
+        Sex::Male => score,
+    };
+    let category = match risk_factors {
+        0 => "LOW",
+        1 => "MODERATE",
+        _ => "HIGH",
+    };
+    let recommendation = match risk_factors {
+        0 => "no antithrombotic therapy",
+        1 => "consider oral anticoagulation",
+        _ => "oral anticoagulation recommended",
+    };
+    let stroke_rate = CHA2DS2_VASC_STROKE_RATE[score as usize];
+
+    Ok(CalculatorResult {
+        calculator: Calculator::Cha2ds2Vasc,
+        score: score as f64,
+        unit: "points".to_string(),
+        category: category.to_string(),
+        interpretation: format!(
+            "Adjusted stroke rate {:.1}% per year; {}",
+            stroke_rate, recommendation
+        ),
+        factors,
+    })
+}
+
+/// Major bleeds per 100 patient-years by HAS-BLED score (5 or more)
+const HAS_BLED_BLEEDING_RATE: [f64; 6] = [1.13, 1.02, 1.88, 3.74, 8.70, 12.50];
+
+/// HAS-BLED bleeding risk score
+pub fn calculate_has_bled(input: &HasBledInput) -> Result<CalculatorResult> {
+    validate(input)?;
+
+    let criteria = [
+        (input.hypertension, "Uncontrolled hypertension"),
+        (input.abnormal_renal_function, "Abnormal renal function"),
+        (input.abnormal_liver_function, "Abnormal liver function"),
+        (input.stroke, "Prior stroke"),
+        (input.bleeding_history, "Bleeding history or predisposition"),
+        (input.labile_inr, "Labile INR"),
+        (input.age > 65, "Age over 65"),
+        (input.antiplatelet_or_nsaid, "Antiplatelet or NSAID use"),
+        (input.alcohol, "Alcohol use"),
+    ];
+    let factors: Vec<String> = criteria
+        .iter()
+        .filter(|(present, _)| *present)
+        .map(|(_, factor)| factor.to_string())
+        .collect();
+    let score = factors.len();
+
+    let category = match score {
+        0 => "LOW",
+        1 | 2 => "MODERATE",
+        _ => "HIGH",
+    };
+    let bleeding_rate = HAS_BLED_BLEEDING_RATE[score.min(5)];
+    let mut interpretation = format!("{:.2} major bleeds per 100 patient-years", bleeding_rate);
+    if score >= 3 {
+        interpretation.push_str("; address modifiable risk factors and review regularly");
+    }
+
+    Ok(CalculatorResult {
+        calculator: Calculator::HasBled,
+        score: score as f64,
+        unit: "points".to_string(),
+        category: category.to_string(),
+        interpretation,
+        factors,
+    })
+}
+
+/// SCORE2 coefficients: age, smoking, SBP, total cholesterol, HDL, then the
+/// interactions of each with age, and the baseline 10-year survival
+struct Score2Model {
+    age: f64,
+    smoking: f64,
+    sbp: f64,
+    total_cholesterol: f64,
+    hdl: f64,
+    smoking_age: f64,
+    sbp_age: f64,
+    total_cholesterol_age: f64,
+    hdl_age: f64,
+    baseline_survival: f64,
+}
+
+const SCORE2_MEN: Score2Model = Score2Model {
+    age: 0.3742,
+    smoking: 0.6012,
+    sbp: 0.2777,
+    total_cholesterol: 0.1458,
+    hdl: -0.2698,
+    smoking_age: -0.0755,
+    sbp_age: -0.0255,
+    total_cholesterol_age: -0.0281,
+    hdl_age: 0.0426,
+    baseline_survival: 0.9605,
+};
+
+const SCORE2_WOMEN: Score2Model = Score2Model {
+    age: 0.4648,
+    smoking: 0.7744,
+    sbp: 0.3131,
+    total_cholesterol: 0.1002,
+    hdl: -0.2606,
+    smoking_age: -0.1088,
+    sbp_age: -0.0277,
+    total_cholesterol_age: -0.0226,
+    hdl_age: 0.0613,
+    baseline_survival: 0.9776,
+};
+
+/// Regional recalibration scales (scale1, scale2)
+fn score2_calibration(region: RiskRegion, sex: Sex) -> (f64, f64) {
+    match (region, sex) {
+        (RiskRegion::Low, Sex::Male) => (-0.5699, 0.7476),
+        (RiskRegion::Low, Sex::Female) => (-0.7380, 0.7019),
+        (RiskRegion::Moderate, Sex::Male) => (-0.1565, 0.8009),
+        (RiskRegion::Moderate, Sex::Female) => (-0.3143, 0.7701),
+        (RiskRegion::High, Sex::Male) => (0.3207, 0.9360),
+        (RiskRegion::High, Sex::Female) => (0.5710, 0.9369),
+        (RiskRegion::VeryHigh, Sex::Male) => (0.5836, 0.8294),
+        (RiskRegion::VeryHigh, Sex::Female) => (0.9412, 0.8329),
+    }
+}
+
+/// SCORE2 10-year risk of fatal and non-fatal cardiovascular events
+pub fn calculate_score2(input: &Score2Input) -> Result<CalculatorResult> {
+    validate(input)?;
+
+    let (total_cholesterol, hdl) = match input.cholesterol_unit {
+        CholesterolUnit::MmolL => (input.total_cholesterol, input.hdl_cholesterol),
+        CholesterolUnit::MgDl => (
+            input.total_cholesterol / CHOLESTEROL_MG_DL_PER_MMOL,
+            input.hdl_cholesterol / CHOLESTEROL_MG_DL_PER_MMOL,
+        ),
+    };
+    if !(2.0..=15.0).contains(&total_cholesterol) || !(0.3..=5.0).contains(&hdl) {
+        return Err(AppError::Validation(
+            "Cholesterol out of range (total 77-580 mg/dL, HDL 12-193 mg/dL)".to_string(),
+        ));
+    }
+
+    let model = match input.sex {
+        Sex::Male => &SCORE2_MEN,
+        Sex::Female => &SCORE2_WOMEN,
+    };
+    let age = (input.age as f64 - 60.0) / 5.0;
+    let smoking = if input.smoker { 1.0 } else { 0.0 };
+    let sbp = (input.systolic_bp - 120.0) / 20.0;
+    let total_cholesterol = total_cholesterol - 6.0;
+    let hdl = (hdl - 1.3) / 0.5;
+
+    let linear = model.age * age
+        + model.smoking * smoking
+        + model.sbp * sbp
+        + model.total_cholesterol * total_cholesterol
+        + model.hdl * hdl
+        + model.smoking_age * smoking * age
+        + model.sbp_age * sbp * age
+        + model.total_cholesterol_age * total_cholesterol * age
+        + model.hdl_age * hdl * age;
+    let uncalibrated = 1.0 - model.baseline_survival.powf(linear.exp());
+
+    let (scale1, scale2) = score2_calibration(input.region, input.sex);
+    let risk = 1.0 - (-(scale1 + scale2 * (-(1.0 - uncalibrated).ln()).ln()).exp()).exp();
+    let risk = round_to(risk * 100.0, 1);
+
+    // ESC 2021 thresholds depend on age
+    let (high, very_high) = if input.age < 50 {
+        (2.5, 7.5)
+    } else {
+        (5.0, 10.0)
+    };
+    let (category, interpretation) = if risk < high {
+        (
+            "LOW_MODERATE",
+            "Low to moderate risk; treatment of risk factors generally not recommended",
+        )
+    } else if risk < very_high {
+        (
+            "HIGH",
+            "High risk; treatment of risk factors should be considered",
+        )
+    } else {
+        (
+            "VERY_HIGH",
+            "Very high risk; treatment of risk factors recommended",
+        )
+    };
+
+    Ok(CalculatorResult {
+        calculator: Calculator::Score2,
+        score: risk,
+        unit: "%".to_string(),
+        category: category.to_string(),
+        interpretation: interpretation.to_string(),
+        factors: Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bmi() {
+        let result = calculate_bmi(&BmiInput {
+            weight_kg: 85.0,
+            height_cm: 175.0,
+        })
+        .unwrap();
+        assert_eq!(result.score, 27.8);
+        assert_eq!(result.category, "OVERWEIGHT");
+
+        assert!(calculate_bmi(&BmiInput {
+            weight_kg: 85.0,
+            height_cm: 5.0,
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn test_egfr() {
+        let male = EgfrInput {
+            age: 50,
+            sex: Sex::Male,
+            creatinine: 1.0,
+            creatinine_unit: CreatinineUnit::MgDl,
+        };
+        let result = calculate_egfr(&male).unwrap();
+        assert_eq!(result.score, 92.0);
+        assert_eq!(result.category, "G1");
+
+        // Same value in µmol/L
+        let umol = EgfrInput {
+            creatinine: 88.42,
+            creatinine_unit: CreatinineUnit::UmolL,
+            ..male.clone()
+        };
+        assert_eq!(calculate_egfr(&umol).unwrap().score, 92.0);
+
+        let female = EgfrInput {
+            age: 70,
+            sex: Sex::Female,
+            creatinine: 1.2,
+            creatinine_unit: CreatinineUnit::MgDl,
+        };
+        let result = calculate_egfr(&female).unwrap();
+        assert_eq!(result.score, 49.0);
+        assert_eq!(result.category, "G3A");
+    }
+
+    #[test]
+    fn test_cha2ds2_vasc() {
+        let input = Cha2ds2VascInput {
+            age: 76,
+            sex: Sex::Female,
+            congestive_heart_failure: false,
+            hypertension: true,
+            diabetes: false,
+            stroke_tia_thromboembolism: false,
+            vascular_disease: false,
+        };
+        let result = calculate_cha2ds2_vasc(&input).unwrap();
+        assert_eq!(result.score, 4.0);
+        assert_eq!(result.category, "HIGH");
+        assert_eq!(result.factors.len(), 3);
+
+        // A woman with no other risk factor is low risk
+        let result = calculate_cha2ds2_vasc(&Cha2ds2VascInput {
+            age: 50,
+            hypertension: false,
+            ..input
+        })
+        .unwrap();
+        assert_eq!(result.score, 1.0);
+        assert_eq!(result.category, "LOW");
+    }
+
+    #[test]
+    fn test_has_bled() {
+        let input = HasBledInput {
+            age: 72,
+            hypertension: true,
+            abnormal_renal_function: false,
+            abnormal_liver_function: false,
+            stroke: false,
+            bleeding_history: false,
+            labile_inr: false,
+            antiplatelet_or_nsaid: true,
+            alcohol: false,
+        };
+        let result = calculate_has_bled(&input).unwrap();
+        assert_eq!(result.score, 3.0);
+        assert_eq!(result.category, "HIGH");
+        assert!(result.interpretation.starts_with("3.74"));
+    }
+
+    #[test]
+    fn test_score2_reference_profile() {
+        // At the centering values the risk is the calibrated baseline
+        let input = Score2Input {
+            age: 60,
+            sex: Sex::Male,
+            smoker: false,
+            systolic_bp: 120.0,
+            total_cholesterol: 6.0,
+            hdl_cholesterol: 1.3,
+            cholesterol_unit: CholesterolUnit::MmolL,
+            region: RiskRegion::Moderate,
+        };
+        let result = calculate_score2(&input).unwrap();
+        assert!((6.0..=6.6).contains(&result.score), "{}", result.score);
+        assert_eq!(result.category, "HIGH");
+
+        // Smoking and higher blood pressure raise the risk
+        let smoker = calculate_score2(&Score2Input {
+            smoker: true,
+            systolic_bp: 160.0,
+            ..input.clone()
+        })
+        .unwrap();
+        assert!(smoker.score > result.score);
+        assert_eq!(smoker.category, "VERY_HIGH");
+
+        // Outside 40-69
+        assert!(calculate_score2(&Score2Input { age: 75, ..input }).is_err());
+    }
+
+    #[test]
+    fn test_calculator_input_tagging() {
+        let input: CalculatorInput = serde_json::from_value(serde_json::json!({
+            "calculator": "BMI",
+            "inputs": { "weight_kg": 70.0, "height_cm": 170.0 }
+        }))
+        .unwrap();
+        assert_eq!(input.calculator(), Calculator::Bmi);
+        assert_eq!(input.calculate().unwrap().category, "NORMAL");
+    }
+}
@@ -0,0 +1,200 @@
+/*!
+ * Patient Contact Propagation
+ *
+ * Patient email and phone are copied into `notification_queue.recipient_*`
+ * when a notification is queued. When the patient record changes, pending
+ * notifications (and failed ones that will still be retried) keep the old
+ * contact unless they are updated here.
+ *
+ * - Changed contact: the recipient field is rewritten with the new value.
+ * - Removed contact: the notification can no longer be delivered and is
+ *   cancelled.
+ *
+ * The blind indexes of the patient's phones and email (`phone_bidx`,
+ * `email_bidx`) are recomputed by `Patient::update_with_existing` in the same
+ * update; only the queued copies are handled here.
+ */
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use sqlx::{Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::models::PatientDto;
+
+/// Notifications that can still be delivered to the stored recipient
+const UNDELIVERED_FILTER: &str =
+    "(status = 'PENDING' OR (status = 'FAILED' AND retry_count < max_retries))";
+
+/// Contact details copied into queued notifications
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ContactDetails {
+    pub email: Option<String>,
+    pub phone: Option<String>,
+}
+
+impl ContactDetails {
+    /// Contact details of a decrypted patient (primary phone only)
+    pub fn from_patient(patient: &PatientDto) -> Self {
+        Self {
+            email: normalize(patient.email.as_deref()),
+            phone: normalize(patient.phone_primary.as_deref()),
+        }
+    }
+}
+
+/// Treat blank values as absent
+fn normalize(value: Option<&str>) -> Option<String> {
+    value
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(str::to_string)
+}
+
+/// Outcome of propagating a contact change
+///
+/// Contains no contact values so it can be written to the audit log as is.
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct ContactPropagation {
+    /// Contact fields that changed ("email", "phone")
+    pub changed_fields: Vec<&'static str>,
+    /// Queued notifications whose recipient was rewritten
+    pub notifications_updated: u64,
+    /// Queued notifications cancelled because the contact was removed
+    pub notifications_cancelled: u64,
+}
+
+impl ContactPropagation {
+    /// Whether any contact field changed
+    pub fn has_changes(&self) -> bool {
+        !self.changed_fields.is_empty()
+    }
+}
+
+/// Propagate changed patient contact details to queued notifications
+///
+/// Runs inside the caller's transaction so the propagation commits (or rolls
+/// back) together with the patient update and sees the same RLS context.
+pub async fn propagate_contact_change(
+    tx: &mut Transaction<'_, Postgres>,
+    patient_id: Uuid,
+    old: &ContactDetails,
+    new: &ContactDetails,
+) -> Result<ContactPropagation> {
+    let mut result = ContactPropagation::default();
+
+    if old.email != new.email {
+        result.changed_fields.push("email");
+        let (updated, cancelled) = propagate_field(
+            tx,
+            patient_id,
+            "recipient_email",
+            "delivery_method = 'EMAIL'",
+            new.email.as_deref(),
+            "Patient email address removed",
+        )
+        .await
+        .context("Failed to propagate patient email change")?;
+        result.notifications_updated += updated;
+        result.notifications_cancelled += cancelled;
+    }
+
+    if old.phone != new.phone {
+        result.changed_fields.push("phone");
+        let (updated, cancelled) = propagate_field(
+            tx,
+            patient_id,
+            "recipient_phone",
+            "delivery_method IN ('SMS', 'WHATSAPP')",
+            new.phone.as_deref(),
+            "Patient phone number removed",
+        )
+        .await
+        .context("Failed to propagate patient phone change")?;
+        result.notifications_updated += updated;
+        result.notifications_cancelled += cancelled;
+    }
+
+    Ok(result)
+}
+
+/// Rewrite (or cancel, when removed) undelivered notifications for one field
+///
+/// Returns `(updated, cancelled)` counts.
+async fn propagate_field(
+    tx: &mut Transaction<'_, Postgres>,
+    patient_id: Uuid,
+    column: &str,
+    method_filter: &str,
+    new_value: Option<&str>,
+    cancel_reason: &str,
+) -> Result<(u64, u64)> {
+    match new_value {
+        Some(value) => {
+            let sql = format!(
+                "UPDATE notification_queue SET {column} = $2, updated_at = NOW() \
+                 WHERE patient_id = $1 AND {method_filter} AND {UNDELIVERED_FILTER}"
+            );
+            let updated = sqlx::query(&sql)
+                .bind(patient_id)
+                .bind(value)
+                .execute(&mut **tx)
+                .await?
+                .rows_affected();
+            Ok((updated, 0))
+        }
+        None => {
+            let sql = format!(
+                "UPDATE notification_queue SET status = 'CANCELLED', error_message = $2, \
+                 updated_at = NOW() \
+                 WHERE patient_id = $1 AND {method_filter} AND {UNDELIVERED_FILTER}"
+            );
+            let cancelled = sqlx::query(&sql)
+                .bind(patient_id)
+                .bind(cancel_reason)
+                .execute(&mut **tx)
+                .await?
+                .rows_affected();
+            Ok((0, cancelled))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_blank_values() {
+        assert_eq!(normalize(None), None);
+        assert_eq!(normalize(Some("")), None);
+        assert_eq!(normalize(Some("   ")), None);
+        assert_eq!(
+            normalize(Some(" mario@example.com ")),
+            Some("mario@example.com".to_string())
+        );
+    }
+
+    #[test]
+    fn test_propagation_has_changes() {
+        let mut result = ContactPropagation::default();
+        assert!(!result.has_changes());
+
+        result.changed_fields.push("email");
+        assert!(result.has_changes());
+    }
+
+    #[test]
+    fn test_propagation_audit_payload_has_no_contact_values() {
+        let result = ContactPropagation {
+            changed_fields: vec!["email", "phone"],
+            notifications_updated: 2,
+            notifications_cancelled: 1,
+        };
+
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(json["changed_fields"], serde_json::json!(["email", "phone"]));
+        assert_eq!(json["notifications_updated"], 2);
+        assert_eq!(json["notifications_cancelled"], 1);
+    }
+}
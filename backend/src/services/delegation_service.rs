@@ -0,0 +1,290 @@
+/*!
+ * Access Delegation Service
+ *
+ * Grants, lists and revokes time-limited access delegations (see
+ * `models::access_delegation`). The window itself is enforced by RLS and the
+ * delegation window middleware; this service only manages the rows.
+ */
+
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::db::rls::begin_with_rls;
+use crate::models::access_delegation::{
+    AccessDelegation, CreateDelegationRequest, DelegationFilter, DelegationStatus,
+};
+use crate::utils::{AppError, Result};
+
+/// Delegation columns plus aggregated scope (requires `GROUP BY d.id`)
+const DELEGATION_SELECT: &str = r#"
+    SELECT d.id, d.grantor_id, d.delegate_id, d.reason, d.starts_at, d.ends_at,
+           d.created_by, d.created_at, d.revoked_at, d.revoked_by,
+           COALESCE(array_agg(s.patient_id) FILTER (WHERE s.patient_id IS NOT NULL), '{}'::uuid[]) AS patient_ids,
+           COALESCE(array_agg(s.appointment_id) FILTER (WHERE s.appointment_id IS NOT NULL), '{}'::uuid[]) AS appointment_ids
+    FROM access_delegations d
+    LEFT JOIN access_delegation_scopes s ON s.delegation_id = d.id
+"#;
+
+/// Locum flag and delegation window state for a user
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DelegationWindow {
+    pub is_locum: bool,
+    pub has_active_delegation: bool,
+}
+
+/// Access delegation service
+pub struct DelegationService {
+    pool: PgPool,
+}
+
+impl DelegationService {
+    /// Create a new delegation service
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Grant a delegation
+    ///
+    /// The grantor and delegate must be active users and the delegate a
+    /// doctor; delegated appointments must belong to the grantor.
+    pub async fn create(
+        &self,
+        req: &CreateDelegationRequest,
+        created_by: Uuid,
+        now: DateTime<Utc>,
+    ) -> Result<AccessDelegation> {
+        req.validate(now).map_err(AppError::Validation)?;
+
+        let mut patient_ids = req.patient_ids.clone();
+        patient_ids.sort();
+        patient_ids.dedup();
+        let mut appointment_ids = req.appointment_ids.clone();
+        appointment_ids.sort();
+        appointment_ids.dedup();
+
+        // RLS context so scope checks see patients and appointments
+        let mut tx = begin_with_rls(&self.pool, created_by).await?;
+
+        let users: Vec<(Uuid, String, bool)> = sqlx::query_as(
+            "SELECT id, role::TEXT, is_active FROM users WHERE id = ANY($1)",
+        )
+        .bind(vec![req.grantor_id, req.delegate_id])
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let find = |id: Uuid| users.iter().find(|(user_id, _, _)| *user_id == id);
+        match find(req.grantor_id) {
+            Some((_, _, true)) => {}
+            Some(_) => return Err(AppError::Validation("Grantor account is inactive".to_string())),
+            None => return Err(AppError::NotFound("Grantor not found".to_string())),
+        }
+        match find(req.delegate_id) {
+            Some((_, role, true)) if role == "DOCTOR" => {}
+            Some((_, _, true)) => {
+                return Err(AppError::Validation("Delegate must be a doctor".to_string()))
+            }
+            Some(_) => return Err(AppError::Validation("Delegate account is inactive".to_string())),
+            None => return Err(AppError::NotFound("Delegate not found".to_string())),
+        }
+
+        if !patient_ids.is_empty() {
+            let found: i64 = sqlx::query_scalar(
+                "SELECT COUNT(*) FROM patients WHERE id = ANY($1)",
+            )
+            .bind(&patient_ids)
+            .fetch_one(&mut *tx)
+            .await?;
+            if found != patient_ids.len() as i64 {
+                return Err(AppError::Validation(
+                    "One or more delegated patients do not exist".to_string(),
+                ));
+            }
+        }
+
+        if !appointment_ids.is_empty() {
+            let found: i64 = sqlx::query_scalar(
+                "SELECT COUNT(*) FROM appointments WHERE id = ANY($1) AND provider_id = $2",
+            )
+            .bind(&appointment_ids)
+            .bind(req.grantor_id)
+            .fetch_one(&mut *tx)
+            .await?;
+            if found != appointment_ids.len() as i64 {
+                return Err(AppError::Validation(
+                    "Delegated appointments must exist and belong to the grantor".to_string(),
+                ));
+            }
+        }
+
+        let delegation_id: Uuid = sqlx::query_scalar(
+            r#"
+            INSERT INTO access_delegations
+                (grantor_id, delegate_id, reason, starts_at, ends_at, created_by)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id
+            "#,
+        )
+        .bind(req.grantor_id)
+        .bind(req.delegate_id)
+        .bind(req.reason.as_deref().map(str::trim).filter(|r| !r.is_empty()))
+        .bind(req.starts_at_or(now))
+        .bind(req.ends_at)
+        .bind(created_by)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO access_delegation_scopes (delegation_id, patient_id)
+            SELECT $1, UNNEST($2::uuid[])
+            "#,
+        )
+        .bind(delegation_id)
+        .bind(&patient_ids)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO access_delegation_scopes (delegation_id, appointment_id)
+            SELECT $1, UNNEST($2::uuid[])
+            "#,
+        )
+        .bind(delegation_id)
+        .bind(&appointment_ids)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        self.get(delegation_id)
+            .await?
+            .ok_or_else(|| AppError::Internal("Delegation not found after insert".to_string()))
+    }
+
+    /// Get a delegation by ID
+    pub async fn get(&self, id: Uuid) -> Result<Option<AccessDelegation>> {
+        let delegation = sqlx::query_as::<_, AccessDelegation>(&format!(
+            "{} WHERE d.id = $1 GROUP BY d.id",
+            DELEGATION_SELECT
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(delegation)
+    }
+
+    /// List delegations, newest window first
+    ///
+    /// `involving` restricts the result to delegations granted to or by that
+    /// user (used for non-admin callers).
+    pub async fn list(
+        &self,
+        filter: &DelegationFilter,
+        involving: Option<Uuid>,
+    ) -> Result<Vec<AccessDelegation>> {
+        let delegations = sqlx::query_as::<_, AccessDelegation>(&format!(
+            r#"
+            {}
+            WHERE ($1::uuid IS NULL OR d.grantor_id = $1)
+              AND ($2::uuid IS NULL OR d.delegate_id = $2)
+              AND ($3::uuid IS NULL OR d.grantor_id = $3 OR d.delegate_id = $3)
+              AND ($4 OR (d.revoked_at IS NULL AND d.ends_at > NOW()))
+            GROUP BY d.id
+            ORDER BY d.starts_at DESC
+            "#,
+            DELEGATION_SELECT
+        ))
+        .bind(filter.grantor_id)
+        .bind(filter.delegate_id)
+        .bind(involving)
+        .bind(filter.include_inactive)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(delegations)
+    }
+
+    /// Revoke a scheduled or active delegation
+    ///
+    /// Returns None if the delegation does not exist.
+    pub async fn revoke(
+        &self,
+        id: Uuid,
+        revoked_by: Uuid,
+        now: DateTime<Utc>,
+    ) -> Result<Option<AccessDelegation>> {
+        let Some(delegation) = self.get(id).await? else {
+            return Ok(None);
+        };
+
+        match delegation.status(now) {
+            DelegationStatus::Scheduled | DelegationStatus::Active => {}
+            DelegationStatus::Expired => {
+                return Err(AppError::Conflict("Delegation has already expired".to_string()))
+            }
+            DelegationStatus::Revoked => {
+                return Err(AppError::Conflict("Delegation is already revoked".to_string()))
+            }
+        }
+
+        sqlx::query(
+            r#"
+            UPDATE access_delegations
+            SET revoked_at = NOW(), revoked_by = $2
+            WHERE id = $1 AND revoked_at IS NULL
+            "#,
+        )
+        .bind(id)
+        .bind(revoked_by)
+        .execute(&self.pool)
+        .await?;
+
+        self.get(id).await
+    }
+
+    /// Locum flag and whether the user has a delegation active right now
+    ///
+    /// Returns None if the user does not exist.
+    pub async fn window(&self, user_id: Uuid) -> Result<Option<DelegationWindow>> {
+        let row: Option<(bool, bool)> = sqlx::query_as(
+            r#"
+            SELECT u.is_locum,
+                   EXISTS (
+                       SELECT 1 FROM access_delegations d
+                       WHERE d.delegate_id = u.id
+                         AND d.revoked_at IS NULL
+                         AND NOW() >= d.starts_at
+                         AND NOW() < d.ends_at
+                   )
+            FROM users u
+            WHERE u.id = $1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|(is_locum, has_active_delegation)| DelegationWindow {
+            is_locum,
+            has_active_delegation,
+        }))
+    }
+
+    /// Mark or unmark a user as a locum account
+    ///
+    /// Returns false if the user does not exist.
+    pub async fn set_locum(&self, user_id: Uuid, is_locum: bool) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE users SET is_locum = $1, updated_at = NOW() WHERE id = $2",
+        )
+        .bind(is_locum)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
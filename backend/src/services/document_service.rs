@@ -6,34 +6,91 @@
  * - Generated document CRUD operations
  * - PDF generation with variable substitution
  * - Document signing and delivery tracking
+ * - Retrying failed generations from the stored original request
+ * - Lab requisitions generated from a lab order
+ * - Referral letters generated from an imaging order
  */
 
+use crate::db::rls::{begin_detached_with_rls, begin_with_rls};
 use crate::{
     models::{
-        CreateDocumentTemplateRequest, DeliverDocumentRequest, DocumentStatistics,
+        BulkGenerateError, BulkGenerateRequest, BulkGenerateResult,
+        ChartSection, CreateDocumentTemplateRequest, CreatePrintBundleRequest,
+        DeliverDocumentRequest, DocumentStatistics,
         DocumentStatus, DocumentStatusCount, DocumentTemplate, DocumentTemplateFilter,
         DocumentTemplateResponse, DocumentTemplateSummary, DocumentType, DocumentTypeCount,
-        GenerateDocumentRequest, GeneratedDocument, GeneratedDocumentFilter,
-        GeneratedDocumentResponse, GeneratedDocumentSummary, ListDocumentTemplatesResponse,
-        ListGeneratedDocumentsResponse, PageOrientation, PageSize, TemplateLanguage,
-        UpdateDocumentTemplateRequest,
+        GenerateDocumentRequest, GenerateImagingReferralRequest, GenerateLabRequisitionRequest,
+        GenerateNrePromemoriaRequest, GenerateSsnPrescriptionRequest, GeneratedDocument,
+        GeneratedDocumentFilter,
+        GeneratedDocumentResponse, GeneratedDocumentSummary, GenerationRequest, ImagingModality,
+        ImagingOrderResponse, ImagingUrgency,
+        ListDocumentTemplatesResponse,
+        LabOrderResponse, LabUrgency, ListGeneratedDocumentsResponse, NrePromemoria,
+        PageOrientation, PageSize, PrescriptionPrintBatch, PrescriptionPrintBatchResponse,
+        PatientDto, PrescriptionPrintLayout, PrescriptionResponse, PrescriptionStatus,
+        PrintBatchStatus, SsnPriority, SSN_MAX_PACKAGES,
+        GenerateReferralLetterRequest, ReferralResponse, ReferralUrgency, TemplateLanguage,
+        UpdateDocumentTemplateRequest, VisitDiagnosisResponse, VisitResponse,
+        generated_document::{CLINICAL_SUMMARY_TEMPLATE_KEY, PRINT_BUNDLE_TEMPLATE_KEY},
     },
-    services::FileUploadService,
-    utils::encryption::EncryptionKey,
+    services::{
+        BrandingService, CacheNamespace, FileUploadService, PatientAllergyService,
+        PatientCommunicationService, PatientService, PrescriptionService, ReferenceCache,
+        VisitAttachmentService, VisitDiagnosisService, VisitService,
+    },
+    utils::{barcode, encryption::EncryptionKey},
 };
 use anyhow::{Context, Result};
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 use chrono::Utc;
 use sha2::{Digest, Sha256};
 use sqlx::PgPool;
+use std::collections::HashMap;
 use std::path::PathBuf;
 use uuid::Uuid;
 
+const PRINT_BATCH_COLUMNS: &str = r#"
+    id, requested_by, status, layout, prescription_ids, file_path, file_size_bytes,
+    file_hash, error_message, created_at, completed_at
+"#;
+
+/// Patient, provider and clinic variables shared by all templates
+struct GenerationContext {
+    patient: serde_json::Value,
+    provider: serde_json::Value,
+    clinic: serde_json::Value,
+}
+
+/// Rendered PDF written to document storage
+struct StoredPdf {
+    filename: String,
+    file_path: String,
+    file_size_bytes: i64,
+    file_hash: String,
+}
+
+/// Outcome of a document generation retry
+#[derive(Debug)]
+pub enum DocumentRetry {
+    /// Document does not exist or is not visible to the user
+    NotFound,
+    /// Document cannot be retried (not FAILED, or no stored request)
+    NotRetryable(String),
+    /// Generation was re-run; the document is GENERATED or FAILED again
+    Retried {
+        document: GeneratedDocumentResponse,
+        attempt: i32,
+        previous_error: Option<String>,
+    },
+}
+
 /// Document Service for managing templates and generated documents
 pub struct DocumentService {
     pool: PgPool,
     encryption_key: EncryptionKey,
     storage_path: PathBuf,
+    /// Cache of template lookups (None: always read from the database)
+    cache: Option<ReferenceCache>,
 }
 
 impl DocumentService {
@@ -43,35 +100,39 @@ impl DocumentService {
             pool,
             encryption_key,
             storage_path,
+            cache: None,
         }
     }
 
-    /// Helper to set RLS context within a transaction
-    async fn set_rls_context(
-        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
-        user_id: Uuid,
-    ) -> Result<()> {
-        // Query the user's role from the database
-        let role: String = sqlx::query_scalar("SELECT role::TEXT FROM users WHERE id = $1")
-            .bind(user_id)
-            .fetch_one(&mut **tx)
-            .await
-            .context("Failed to fetch user role for RLS context")?;
+    /// Cache template lookups, invalidated when a template is written
+    pub fn with_cache(mut self, cache: ReferenceCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
 
-        // Set RLS context variables using set_config() for parameterized queries
-        sqlx::query("SELECT set_config('app.current_user_id', $1, true)")
-            .bind(user_id.to_string())
-            .execute(&mut **tx)
+    /// Cached result of a template lookup
+    async fn cached_template(&self, key: &str) -> Option<Option<DocumentTemplateResponse>> {
+        self.cache
+            .as_ref()?
+            .get(CacheNamespace::DocumentTemplates, key)
             .await
-            .context("Failed to set RLS user context")?;
+    }
 
-        sqlx::query("SELECT set_config('app.current_user_role', $1, true)")
-            .bind(&role)
-            .execute(&mut **tx)
-            .await
-            .context("Failed to set RLS role context")?;
+    /// Cache the result of a template lookup
+    async fn cache_template(&self, key: &str, template: &Option<DocumentTemplateResponse>) {
+        if let Some(cache) = &self.cache {
+            cache
+                .set(CacheNamespace::DocumentTemplates, key, template)
+                .await;
+        }
+    }
 
-        Ok(())
+    /// Drop cached template lookups after a write (a change to one template
+    /// can change which one is the default)
+    async fn invalidate_templates(&self) {
+        if let Some(cache) = &self.cache {
+            cache.invalidate_all(CacheNamespace::DocumentTemplates).await;
+        }
     }
 
     // ==================== Document Template Operations ====================
@@ -141,11 +202,18 @@ impl DocumentService {
         .await
         .context("Failed to create document template")?;
 
+        self.invalidate_templates().await;
+
         Ok(DocumentTemplateResponse::from(template))
     }
 
     /// Get document template by ID
     pub async fn get_template(&self, id: Uuid) -> Result<Option<DocumentTemplateResponse>> {
+        let cache_key = format!("id:{}", id);
+        if let Some(cached) = self.cached_template(&cache_key).await {
+            return Ok(cached);
+        }
+
         let template = sqlx::query_as!(
             DocumentTemplate,
             r#"
@@ -166,11 +234,19 @@ impl DocumentService {
         .await
         .context("Failed to fetch document template")?;
 
-        Ok(template.map(DocumentTemplateResponse::from))
+        let template = template.map(DocumentTemplateResponse::from);
+        self.cache_template(&cache_key, &template).await;
+
+        Ok(template)
     }
 
     /// Get document template by key
     pub async fn get_template_by_key(&self, key: &str) -> Result<Option<DocumentTemplateResponse>> {
+        let cache_key = format!("key:{}", key);
+        if let Some(cached) = self.cached_template(&cache_key).await {
+            return Ok(cached);
+        }
+
         let template = sqlx::query_as!(
             DocumentTemplate,
             r#"
@@ -191,7 +267,10 @@ impl DocumentService {
         .await
         .context("Failed to fetch document template by key")?;
 
-        Ok(template.map(DocumentTemplateResponse::from))
+        let template = template.map(DocumentTemplateResponse::from);
+        self.cache_template(&cache_key, &template).await;
+
+        Ok(template)
     }
 
     /// Get default template for a document type and language
@@ -200,6 +279,15 @@ impl DocumentService {
         document_type: DocumentType,
         language: TemplateLanguage,
     ) -> Result<Option<DocumentTemplateResponse>> {
+        let cache_key = format!(
+            "default:{}:{}",
+            document_type.as_str(),
+            language.as_str()
+        );
+        if let Some(cached) = self.cached_template(&cache_key).await {
+            return Ok(cached);
+        }
+
         let template = sqlx::query_as!(
             DocumentTemplate,
             r#"
@@ -221,7 +309,10 @@ impl DocumentService {
         .await
         .context("Failed to fetch default template")?;
 
-        Ok(template.map(DocumentTemplateResponse::from))
+        let template = template.map(DocumentTemplateResponse::from);
+        self.cache_template(&cache_key, &template).await;
+
+        Ok(template)
     }
 
     /// List document templates with filtering
@@ -381,6 +472,8 @@ impl DocumentService {
         .await
         .context("Failed to update document template")?;
 
+        self.invalidate_templates().await;
+
         Ok(DocumentTemplateResponse::from(template))
     }
 
@@ -399,12 +492,18 @@ impl DocumentService {
         .await
         .context("Failed to delete document template")?;
 
+        self.invalidate_templates().await;
+
         Ok(())
     }
 
     // ==================== Generated Document Operations ====================
 
     /// Generate a new document from a template
+    ///
+    /// If rendering or storing the PDF fails, the document is recorded as
+    /// FAILED (with the error and the original request) so that it can be
+    /// retried with `retry_document`, and the error is returned.
     pub async fn generate_document(
         &self,
         data: GenerateDocumentRequest,
@@ -412,211 +511,115 @@ impl DocumentService {
     ) -> Result<GeneratedDocumentResponse> {
         tracing::debug!("Starting document generation for template_id={}, provider_id={}", data.template_id, provider_id);
 
-        // Get template
-        let template = self
-            .get_template(data.template_id)
-            .await
-            .context("Failed to fetch template")?
-            .ok_or_else(|| anyhow::anyhow!("Template not found"))?;
-
-        tracing::debug!("Template retrieved: {}", template.template_name);
-
-        // Start transaction for RLS context to fetch patient data
-        let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-
-        tracing::debug!("Transaction started, setting RLS context for provider_id={}", provider_id);
-
-        // Set RLS context
-        Self::set_rls_context(&mut tx, provider_id).await
-            .context("Failed to set RLS context")?;
+        let (template, variables) = self.build_generation_variables(&data, provider_id).await?;
+        let generation_request = self.encrypt_generation_request(&GenerationRequest::Generate(data.clone()))?;
+
+        let StoredPdf { filename, file_path, file_size_bytes, file_hash } =
+            match self.render_and_store(&template, &variables).await {
+                Ok(stored) => stored,
+                Err(e) => {
+                    let document_id = self
+                        .record_failed_generation(&data, &template, provider_id, &generation_request, &e)
+                        .await?;
+                    return Err(e.context(format!(
+                        "Document {} recorded as FAILED and can be retried",
+                        document_id
+                    )));
+                }
+            };
 
-        tracing::debug!("RLS context set successfully");
+        // Encrypt generation data (contains PHI)
+        let encrypted_variables = self.encryption_key.encrypt_json(&variables)
+            .context("Failed to encrypt generation data")?;
+        let generation_data_json = serde_json::json!({"encrypted": encrypted_variables});
 
-        // Fetch patient data from database within RLS context (data is encrypted)
-        let patient_encrypted = sqlx::query!(
-            r#"
-            SELECT id, first_name, last_name, middle_name, date_of_birth,
-                   gender, fiscal_code, email, phone_primary
-            FROM patients
-            WHERE id = $1
-            "#,
-            data.patient_id
-        )
-        .fetch_one(&mut *tx)
-        .await
-        .context("Failed to fetch patient for document generation")?;
+        // Start another transaction for inserting the document
+        let mut tx = begin_with_rls(&self.pool, provider_id)
+            .await
+            .context("Failed to begin document insert transaction")?;
 
-        // Decrypt patient data
-        let patient_first_name = self.encryption_key.decrypt(&patient_encrypted.first_name)
-            .context("Failed to decrypt patient first_name")?;
-        let patient_last_name = self.encryption_key.decrypt(&patient_encrypted.last_name)
-            .context("Failed to decrypt patient last_name")?;
-        let patient_middle_name = patient_encrypted.middle_name
-            .as_ref()
-            .map(|m| self.encryption_key.decrypt(m))
-            .transpose()
-            .context("Failed to decrypt patient middle_name")?;
-        let patient_date_of_birth = self.encryption_key.decrypt(&patient_encrypted.date_of_birth)
-            .context("Failed to decrypt patient date_of_birth")?;
-        let patient_fiscal_code = patient_encrypted.fiscal_code
-            .as_ref()
-            .map(|f| self.encryption_key.decrypt(f))
-            .transpose()
-            .context("Failed to decrypt patient fiscal_code")?;
-        let patient_email = patient_encrypted.email
-            .as_ref()
-            .map(|e| self.encryption_key.decrypt(e))
-            .transpose()
-            .context("Failed to decrypt patient email")?;
-        let patient_phone = patient_encrypted.phone_primary
-            .as_ref()
-            .map(|p| self.encryption_key.decrypt(p))
-            .transpose()
-            .context("Failed to decrypt patient phone")?;
+        tracing::debug!("RLS context set for document insert");
 
-        // Fetch provider (user) data for template
-        let provider = sqlx::query!(
+        // Create database record
+        let document = sqlx::query_as!(
+            GeneratedDocument,
             r#"
-            SELECT id, first_name, last_name, email
-            FROM users
-            WHERE id = $1
+            INSERT INTO generated_documents (
+                template_id, patient_id, visit_id, visit_date, provider_id,
+                document_type, document_title, document_filename,
+                file_path, file_size_bytes, file_hash,
+                template_version, generation_data,
+                status, expires_at,
+                created_by
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
+            RETURNING
+                id, template_id, patient_id, visit_id, visit_date, provider_id,
+                document_type, document_title, document_filename,
+                file_path, file_size_bytes, file_hash,
+                template_version, generation_data,
+                status, generation_error,
+                delivered_to, delivered_at,
+                expires_at, deleted_at,
+                is_signed, signature_hash, signed_at, signed_by,
+                created_at, updated_at, created_by, updated_by
             "#,
+            data.template_id,
+            data.patient_id,
+            data.visit_id,
+            data.visit_date,
+            provider_id,
+            template.document_type.as_str(),
+            data.document_title,
+            filename,
+            file_path,
+            file_size_bytes,
+            file_hash,
+            template.version,
+            generation_data_json,
+            DocumentStatus::Generated.as_str(),
+            data.expires_at,
             provider_id
         )
         .fetch_one(&mut *tx)
         .await
-        .context("Failed to fetch provider for document generation")?;
+        .context("Failed to create document record")?;
 
-        // Fetch clinic settings from system_settings table
-        let clinic_settings = sqlx::query!(
-            r#"
-            SELECT setting_key, setting_value
-            FROM system_settings
-            WHERE setting_group = 'clinic'
-            "#
-        )
-        .fetch_all(&mut *tx)
-        .await
-        .context("Failed to fetch clinic settings")?;
+        // Keep the original request so the document can be regenerated later
+        sqlx::query("UPDATE generated_documents SET generation_request = $2 WHERE id = $1")
+            .bind(document.id)
+            .bind(&generation_request)
+            .execute(&mut *tx)
+            .await
+            .context("Failed to store generation request")?;
 
-        // Commit transaction after fetching data
-        tx.commit().await.context("Failed to commit data fetch transaction")?;
+        // Commit transaction
+        tx.commit().await.context("Failed to commit transaction")?;
 
-        tracing::debug!("Patient and provider data fetched successfully");
+        Ok(GeneratedDocumentResponse::from(document))
+    }
 
-        // Fetch practice logo (outside transaction)
-        let logo_data = match FileUploadService::get_logo(&self.pool).await {
-            Ok(Some(logo)) => {
-                // Read logo file and convert to base64 data URI
-                match FileUploadService::read_file(&logo.storage_path).await {
-                    Ok(bytes) => {
-                        let base64_data = BASE64.encode(&bytes);
-                        let data_uri = format!("data:{};base64,{}", logo.mime_type, base64_data);
-                        tracing::debug!("Logo loaded successfully, size: {} bytes", bytes.len());
-                        Some(data_uri)
-                    }
-                    Err(e) => {
-                        tracing::warn!("Failed to read logo file: {}", e);
-                        None
-                    }
-                }
-            }
-            Ok(None) => {
-                tracing::debug!("No practice logo configured");
-                None
-            }
-            Err(e) => {
-                tracing::warn!("Failed to fetch logo: {}", e);
-                None
-            }
-        };
+    /// Load the template and build the variables for a generation request
+    async fn build_generation_variables(
+        &self,
+        data: &GenerateDocumentRequest,
+        provider_id: Uuid,
+    ) -> Result<(DocumentTemplateResponse, serde_json::Value)> {
+        // Get template
+        let template = self
+            .get_template(data.template_id)
+            .await
+            .context("Failed to fetch template")?
+            .ok_or_else(|| anyhow::anyhow!("Template not found"))?;
 
-        // Build patient data for template (using decrypted values)
-        let patient_full_name = if let Some(ref middle) = patient_middle_name {
-            format!("{} {} {}", patient_first_name, middle, patient_last_name)
-        } else {
-            format!("{} {}", patient_first_name, patient_last_name)
-        };
+        tracing::debug!("Template retrieved: {}", template.template_name);
 
-        let patient_data = serde_json::json!({
-            "id": patient_encrypted.id.to_string(),
-            "first_name": patient_first_name,
-            "last_name": patient_last_name,
-            "middle_name": patient_middle_name,
-            "full_name": patient_full_name,
-            "date_of_birth": patient_date_of_birth,
-            "gender": patient_encrypted.gender,
-            "fiscal_code": patient_fiscal_code.unwrap_or_else(|| "none".to_string()),
-            "email": patient_email,
-            "phone": patient_phone,
-        });
+        let GenerationContext { patient: patient_data, provider: provider_data, clinic: clinic_data } =
+            self.load_generation_context(data.patient_id, provider_id).await?;
 
-        // Build provider data for template
-        let provider_full_name = format!("{} {}", provider.first_name, provider.last_name);
-        let provider_data = serde_json::json!({
-            "id": provider.id.to_string(),
-            "first_name": provider.first_name,
-            "last_name": provider.last_name,
-            "full_name": provider_full_name,
-            "email": provider.email,
-            // Default values for fields that may not exist in users table
-            "specialization": "Medico Chirurgo",
-            "license_number": "N/D",
-        });
-
-        // Build clinic data from system_settings
-        let mut clinic_name = "Studio Medico".to_string();
-        let mut clinic_address = String::new();
-        let mut clinic_phone = String::new();
-        let mut clinic_email = String::new();
-        let mut clinic_vat = String::new();
-        let mut clinic_website = String::new();
-        let mut clinic_fax = String::new();
-
-        for setting in &clinic_settings {
-            // Extract string value from JSON (removing quotes if present)
-            let value = match &setting.setting_value {
-                serde_json::Value::String(s) => s.clone(),
-                v => v.to_string().trim_matches('"').to_string(),
-            };
-            match setting.setting_key.as_str() {
-                "clinic.name" => clinic_name = value,
-                "clinic.address" => clinic_address = value,
-                "clinic.phone" => clinic_phone = value,
-                "clinic.email" => clinic_email = value,
-                "clinic.vat_number" => clinic_vat = value,
-                "clinic.website" => clinic_website = value,
-                "clinic.fax" => clinic_fax = value,
-                _ => {}
-            }
-        }
-
-        // Parse address into components if possible (format: "street, city, province")
-        let address_parts: Vec<&str> = clinic_address.split(',').map(|s| s.trim()).collect();
-        let (street, city, province) = match address_parts.as_slice() {
-            [street, city, province, ..] => (street.to_string(), city.to_string(), province.to_string()),
-            [street, city] => (street.to_string(), city.to_string(), String::new()),
-            [street] => (street.to_string(), String::new(), String::new()),
-            [] => (String::new(), String::new(), String::new()),
-        };
-
-        let clinic_data = serde_json::json!({
-            "name": clinic_name,
-            "address": street,
-            "full_address": clinic_address,
-            "city": city,
-            "province": province,
-            "phone": clinic_phone,
-            "fax": clinic_fax,
-            "email": clinic_email,
-            "website": clinic_website,
-            "vat_number": clinic_vat,
-            "logo": logo_data,
-        });
-
-        // Build document metadata
-        let document_data = serde_json::json!({
-            "date": Utc::now().format("%d/%m/%Y").to_string(),
+        // Build document metadata
+        let document_data = serde_json::json!({
+            "date": Utc::now().format("%d/%m/%Y").to_string(),
         });
 
         // Merge all data with additional_data
@@ -654,6 +657,16 @@ impl DocumentService {
                     "fasting": false,
                 }));
             }
+            if !map.contains_key("imaging") {
+                map.insert("imaging".to_string(), serde_json::json!({
+                    "modality": "",
+                    "body_part": "",
+                    "exam": "",
+                    "clinical_question": "",
+                    "urgency": "",
+                    "contrast": false,
+                }));
+            }
             if !map.contains_key("visit") {
                 map.insert("visit".to_string(), serde_json::json!({
                     "date": "",
@@ -698,6 +711,14 @@ impl DocumentService {
                     "urgency": "",
                     "fasting": false,
                 },
+                "imaging": {
+                    "modality": "",
+                    "body_part": "",
+                    "exam": "",
+                    "clinical_question": "",
+                    "urgency": "",
+                    "contrast": false,
+                },
                 "visit": {
                     "date": "",
                     "chief_complaint": "",
@@ -717,325 +738,853 @@ impl DocumentService {
 
         tracing::debug!("Merged patient, provider, clinic data with template variables");
 
-        // Perform variable substitution on main template
-        let rendered_html = self.substitute_variables(&template.template_html, &variables)
-            .context("Failed to substitute template variables")?;
+        Ok((template, variables))
+    }
 
-        // Also substitute variables in header and footer
-        let rendered_header = template.header_html
-            .as_ref()
-            .map(|h| self.substitute_variables(h, &variables))
-            .transpose()
-            .context("Failed to substitute header variables")?;
-        let rendered_footer = template.footer_html
-            .as_ref()
-            .map(|f| self.substitute_variables(f, &variables))
-            .transpose()
-            .context("Failed to substitute footer variables")?;
+    /// Encrypt a generation request for storage (it may contain PHI)
+    fn encrypt_generation_request(&self, request: &GenerationRequest) -> Result<serde_json::Value> {
+        let encrypted = self
+            .encryption_key
+            .encrypt_json(request)
+            .context("Failed to encrypt generation request")?;
+        Ok(serde_json::json!({"encrypted": encrypted}))
+    }
 
-        tracing::debug!("Template variables substituted successfully");
+    /// Decrypt a stored generation request
+    fn decrypt_generation_request(&self, stored: &serde_json::Value) -> Result<GenerationRequest> {
+        let encrypted = stored
+            .get("encrypted")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow::anyhow!("Stored generation request is malformed"))?;
+        self.encryption_key
+            .decrypt_json(encrypted)
+            .context("Failed to decrypt generation request")
+    }
 
-        // Generate PDF
-        let pdf_bytes = self.render_pdf_from_html(
-            &rendered_html,
-            rendered_header.as_deref(),
-            rendered_footer.as_deref(),
-            template.css_styles.as_deref(),
-            template.page_size,
-            template.page_orientation,
-            template.margin_top_mm,
-            template.margin_bottom_mm,
-            template.margin_left_mm,
-            template.margin_right_mm,
-        ).context("Failed to render PDF from HTML")?;
+    /// Record a document whose PDF could not be rendered or stored
+    ///
+    /// Committed on its own, as the request that hit the failure is rolled back.
+    async fn record_failed_generation(
+        &self,
+        data: &GenerateDocumentRequest,
+        template: &DocumentTemplateResponse,
+        provider_id: Uuid,
+        generation_request: &serde_json::Value,
+        error: &anyhow::Error,
+    ) -> Result<Uuid> {
+        let mut tx = begin_detached_with_rls(&self.pool, provider_id)
+            .await
+            .context("Failed to begin transaction")?;
 
-        tracing::debug!("PDF generated successfully, size: {} bytes", pdf_bytes.len());
+        // document_filename is assigned by the generate_document_filename trigger
+        let document_id: Uuid = sqlx::query_scalar(
+            r#"
+            INSERT INTO generated_documents (
+                template_id, patient_id, visit_id, visit_date, provider_id,
+                document_type, document_title, document_filename, file_path,
+                template_version, generation_request,
+                status, generation_error, expires_at, created_by
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, '', '', $8, $9, $10, $11, $12, $5)
+            RETURNING id
+            "#,
+        )
+        .bind(data.template_id)
+        .bind(data.patient_id)
+        .bind(data.visit_id)
+        .bind(data.visit_date)
+        .bind(provider_id)
+        .bind(template.document_type.as_str())
+        .bind(&data.document_title)
+        .bind(template.version)
+        .bind(generation_request)
+        .bind(DocumentStatus::Failed.as_str())
+        .bind(format!("{:#}", error))
+        .bind(data.expires_at)
+        .fetch_one(&mut *tx)
+        .await
+        .context("Failed to record failed document generation")?;
 
-        // Calculate file hash
-        let file_hash = self.calculate_hash(&pdf_bytes);
+        tx.commit().await.context("Failed to commit transaction")?;
 
-        // Generate filename
-        let filename = format!(
-            "{}_{}.pdf",
-            template.document_type.as_str().to_lowercase(),
-            Utc::now().format("%Y%m%d_%H%M%S")
+        tracing::warn!("Document generation failed, recorded as FAILED document {}", document_id);
+
+        Ok(document_id)
+    }
+
+    /// Generate a document from one template for each of several patients
+    ///
+    /// Documents are generated one after the other; a failure for one
+    /// patient is reported in the result and does not stop the others.
+    /// Every document is titled with the prefix and today's date.
+    pub async fn bulk_generate(
+        &self,
+        data: BulkGenerateRequest,
+        provider_id: Uuid,
+    ) -> BulkGenerateResult {
+        let title = format!(
+            "{} - {}",
+            data.title_prefix.trim(),
+            Utc::now().date_naive().format("%Y-%m-%d")
         );
+        let mut successful = Vec::new();
+        let mut failed = Vec::new();
+
+        for patient_id in &data.patient_ids {
+            let request = GenerateDocumentRequest {
+                template_id: data.template_id,
+                patient_id: *patient_id,
+                document_title: title.clone(),
+                visit_id: None,
+                visit_date: None,
+                additional_data: data.common_data.clone(),
+                expires_at: None,
+            };
+            match self.generate_document(request, provider_id).await {
+                Ok(document) => successful.push(document.id),
+                Err(e) => failed.push(BulkGenerateError {
+                    patient_id: *patient_id,
+                    error: format!("{:#}", e),
+                }),
+            }
+        }
 
-        // Store file
-        let file_path = self.store_file(&filename, &pdf_bytes).await
-            .context("Failed to store PDF file")?;
+        BulkGenerateResult {
+            total_requested: data.patient_ids.len(),
+            total_successful: successful.len(),
+            total_failed: failed.len(),
+            successful,
+            failed,
+        }
+    }
 
-        tracing::debug!("PDF stored at: {}", file_path);
+    // ==================== Generation Retry ====================
 
-        // Encrypt generation data (contains PHI)
-        let encrypted_variables = self.encryption_key.encrypt_json(&variables)
-            .context("Failed to encrypt generation data")?;
-        let generation_data_json = serde_json::json!({"encrypted": encrypted_variables});
+    /// Re-run a FAILED generation from its original request
+    ///
+    /// The same document row is reused (id and audit trail are preserved): it
+    /// moves FAILED -> GENERATING -> GENERATED, or back to FAILED with the new
+    /// error. Patient, provider and template data are reloaded, so fixes made
+    /// since the failure (e.g. a corrected template) are picked up.
+    pub async fn retry_document(&self, id: Uuid, user_id: Uuid) -> Result<DocumentRetry> {
+        let mut tx = begin_with_rls(&self.pool, user_id)
+            .await
+            .context("Failed to begin transaction")?;
 
-        // Start another transaction for inserting the document
-        let mut tx = self.pool.begin().await.context("Failed to begin document insert transaction")?;
+        let row: Option<(String, Option<serde_json::Value>, Option<String>, Uuid, Uuid)> =
+            sqlx::query_as(
+                r#"
+                SELECT status, generation_request, generation_error, patient_id, provider_id
+                FROM generated_documents
+                WHERE id = $1
+                FOR UPDATE
+                "#,
+            )
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await
+            .context("Failed to fetch document")?;
 
-        tracing::debug!("Insert transaction started, setting RLS context for provider_id={}", provider_id);
+        let Some((status, stored_request, previous_error, patient_id, provider_id)) = row else {
+            return Ok(DocumentRetry::NotFound);
+        };
 
-        // Set RLS context again for the new transaction
-        Self::set_rls_context(&mut tx, provider_id).await
-            .context("Failed to set RLS context for document insert")?;
+        if status != DocumentStatus::Failed.as_str() {
+            return Ok(DocumentRetry::NotRetryable(format!(
+                "Only FAILED documents can be retried (status is {})",
+                status
+            )));
+        }
 
-        tracing::debug!("RLS context set for document insert");
+        let Some(stored_request) = stored_request else {
+            return Ok(DocumentRetry::NotRetryable(
+                "The original generation request was not recorded for this document".to_string(),
+            ));
+        };
+        let request = self.decrypt_generation_request(&stored_request)?;
 
-        // Create database record
-        let document = sqlx::query_as!(
-            GeneratedDocument,
+        let attempt: i32 = sqlx::query_scalar(
             r#"
-            INSERT INTO generated_documents (
-                template_id, patient_id, visit_id, visit_date, provider_id,
-                document_type, document_title, document_filename,
-                file_path, file_size_bytes, file_hash,
-                template_version, generation_data,
-                status, expires_at,
-                created_by
-            )
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
-            RETURNING
-                id, template_id, patient_id, visit_id, visit_date, provider_id,
-                document_type, document_title, document_filename,
-                file_path, file_size_bytes, file_hash,
-                template_version, generation_data,
-                status, generation_error,
-                delivered_to, delivered_at,
-                expires_at, deleted_at,
-                is_signed, signature_hash, signed_at, signed_by,
-                created_at, updated_at, created_by, updated_by
+            UPDATE generated_documents
+            SET status = $2,
+                generation_error = NULL,
+                retry_count = retry_count + 1,
+                last_retried_at = NOW(),
+                last_retried_by = $3,
+                updated_by = $3
+            WHERE id = $1
+            RETURNING retry_count
             "#,
-            data.template_id,
-            data.patient_id,
-            data.visit_id,
-            data.visit_date,
-            provider_id,
-            template.document_type.as_str(),
-            data.document_title,
-            filename,
-            file_path,
-            pdf_bytes.len() as i64,
-            file_hash,
-            template.version,
-            generation_data_json,
-            DocumentStatus::Generated.as_str(),
-            data.expires_at,
-            provider_id
         )
+        .bind(id)
+        .bind(DocumentStatus::Generating.as_str())
+        .bind(user_id)
         .fetch_one(&mut *tx)
         .await
-        .context("Failed to create document record")?;
+        .context("Failed to start document retry")?;
 
-        // Commit transaction
         tx.commit().await.context("Failed to commit transaction")?;
 
-        Ok(GeneratedDocumentResponse::from(document))
+        // Regenerate as the original provider so the output matches the request
+        let rendered: Result<(StoredPdf, serde_json::Value, Option<i32>)> = match &request {
+            GenerationRequest::Generate(data) => {
+                async {
+                    let (template, variables) =
+                        self.build_generation_variables(data, provider_id).await?;
+                    let stored = self.render_and_store(&template, &variables).await?;
+                    Ok((stored, variables, Some(template.version)))
+                }
+                .await
+            }
+            GenerationRequest::PrintBundle(bundle) => self
+                .render_print_bundle(patient_id, bundle, provider_id)
+                .await
+                .map(|(stored, variables)| (stored, variables, None)),
+        };
+
+        let mut tx = begin_with_rls(&self.pool, user_id)
+            .await
+            .context("Failed to begin transaction")?;
+
+        let document = match rendered {
+            Ok((stored, variables, template_version)) => {
+                let encrypted_variables = self
+                    .encryption_key
+                    .encrypt_json(&variables)
+                    .context("Failed to encrypt generation data")?;
+
+                sqlx::query_as::<_, GeneratedDocument>(
+                    r#"
+                    UPDATE generated_documents
+                    SET status = $2,
+                        document_filename = $3,
+                        file_path = $4,
+                        file_size_bytes = $5,
+                        file_hash = $6,
+                        generation_data = $7,
+                        template_version = COALESCE($8, template_version),
+                        updated_by = $9
+                    WHERE id = $1 AND status = 'GENERATING'
+                    RETURNING
+                        id, template_id, patient_id, visit_id, visit_date, provider_id,
+                        document_type, document_title, document_filename,
+                        file_path, file_size_bytes, file_hash,
+                        template_version, generation_data,
+                        status, generation_error,
+                        delivered_to, delivered_at,
+                        expires_at, deleted_at,
+                        is_signed, signature_hash, signed_at, signed_by,
+                        created_at, updated_at, created_by, updated_by
+                    "#,
+                )
+                .bind(id)
+                .bind(DocumentStatus::Generated.as_str())
+                .bind(&stored.filename)
+                .bind(&stored.file_path)
+                .bind(stored.file_size_bytes)
+                .bind(&stored.file_hash)
+                .bind(serde_json::json!({"encrypted": encrypted_variables}))
+                .bind(template_version)
+                .bind(user_id)
+                .fetch_optional(&mut *tx)
+                .await
+                .context("Failed to update retried document")?
+            }
+            Err(e) => {
+                tracing::warn!("Retry {} of document {} failed: {:#}", attempt, id, e);
+
+                sqlx::query_as::<_, GeneratedDocument>(
+                    r#"
+                    UPDATE generated_documents
+                    SET status = $2, generation_error = $3, updated_by = $4
+                    WHERE id = $1 AND status = 'GENERATING'
+                    RETURNING
+                        id, template_id, patient_id, visit_id, visit_date, provider_id,
+                        document_type, document_title, document_filename,
+                        file_path, file_size_bytes, file_hash,
+                        template_version, generation_data,
+                        status, generation_error,
+                        delivered_to, delivered_at,
+                        expires_at, deleted_at,
+                        is_signed, signature_hash, signed_at, signed_by,
+                        created_at, updated_at, created_by, updated_by
+                    "#,
+                )
+                .bind(id)
+                .bind(DocumentStatus::Failed.as_str())
+                .bind(format!("{:#}", e))
+                .bind(user_id)
+                .fetch_optional(&mut *tx)
+                .await
+                .context("Failed to mark retried document as failed")?
+            }
+        };
+
+        tx.commit().await.context("Failed to commit transaction")?;
+
+        // Only missing if the janitor failed the row while it was rendering
+        let document = document
+            .ok_or_else(|| anyhow::anyhow!("Document {} changed state during retry", id))?;
+
+        Ok(DocumentRetry::Retried {
+            document: GeneratedDocumentResponse::from(document),
+            attempt,
+            previous_error,
+        })
     }
 
-    /// Get generated document by ID
-    pub async fn get_document(&self, id: Uuid, user_id: Uuid) -> Result<Option<GeneratedDocumentResponse>> {
-        // Start transaction for RLS context
-        let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
+    // ==================== Lab Requisitions ====================
 
-        // Set RLS context
-        Self::set_rls_context(&mut tx, user_id).await?;
+    /// Generate the requisition PDF for a lab order
+    ///
+    /// The order must already have its requisition number (see
+    /// `LabService::prepare_requisition`). Uses the requested LAB_REQUEST
+    /// template, or the default one for the language, and fills the `lab`
+    /// variables from the order; the document goes through
+    /// `generate_document`, so a failed requisition can be retried.
+    pub async fn generate_lab_requisition(
+        &self,
+        order: &LabOrderResponse,
+        request: &GenerateLabRequisitionRequest,
+        provider_id: Uuid,
+    ) -> Result<GeneratedDocumentResponse> {
+        let template = match request.template_id {
+            Some(template_id) => self
+                .get_template(template_id)
+                .await?
+                .filter(|t| t.document_type == DocumentType::LabRequest)
+                .ok_or_else(|| anyhow::anyhow!("Lab request template {} not found", template_id))?,
+            None => self
+                .get_default_template(DocumentType::LabRequest, request.language)
+                .await?
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "No default lab request template for language '{}'",
+                        request.language.as_str()
+                    )
+                })?,
+        };
 
-        let document = sqlx::query_as!(
-            GeneratedDocument,
-            r#"
-            SELECT
-                id, template_id, patient_id, visit_id, visit_date, provider_id,
-                document_type, document_title, document_filename,
-                file_path, file_size_bytes, file_hash,
-                template_version, generation_data,
-                status, generation_error,
-                delivered_to, delivered_at,
-                expires_at, deleted_at,
-                is_signed, signature_hash, signed_at, signed_by,
-                created_at, updated_at, created_by, updated_by
-            FROM generated_documents
-            WHERE id = $1 AND status != 'DELETED'
-            "#,
-            id
+        let lab = lab_requisition_context(order, request)?;
+        let requisition_number = order.requisition_number.as_deref().unwrap_or_default();
+        let document_title = match request.language {
+            TemplateLanguage::Italian => format!("Richiesta esami {}", requisition_number),
+            TemplateLanguage::English => format!("Lab requisition {}", requisition_number),
+        };
+
+        self.generate_document(
+            GenerateDocumentRequest {
+                template_id: template.id,
+                patient_id: order.patient_id,
+                document_title,
+                visit_id: order.visit_id,
+                visit_date: order.visit_date,
+                additional_data: Some(serde_json::json!({ "lab": lab })),
+                expires_at: None,
+            },
+            provider_id,
         )
-        .fetch_optional(&mut *tx)
         .await
-        .context("Failed to fetch generated document")?;
+    }
 
-        tx.commit().await.context("Failed to commit transaction")?;
+    // ==================== Imaging Referral Letters ====================
 
-        Ok(document.map(GeneratedDocumentResponse::from))
+    /// Generate the referral letter for an imaging order
+    ///
+    /// Uses the requested REFERRAL_LETTER template; otherwise the imaging
+    /// referral template for Italian, falling back to the default referral
+    /// template for the language. Fills both the `imaging` variables and the
+    /// generic `referral` ones, so any referral template prints the order.
+    pub async fn generate_imaging_referral(
+        &self,
+        order: &ImagingOrderResponse,
+        request: &GenerateImagingReferralRequest,
+        provider_id: Uuid,
+    ) -> Result<GeneratedDocumentResponse> {
+        let template = match request.template_id {
+            Some(template_id) => self
+                .get_template(template_id)
+                .await?
+                .filter(|t| t.document_type == DocumentType::ReferralLetter)
+                .ok_or_else(|| anyhow::anyhow!("Referral template {} not found", template_id))?,
+            None => {
+                let imaging_template = match request.language {
+                    TemplateLanguage::Italian => self
+                        .get_template_by_key(IMAGING_REFERRAL_TEMPLATE_KEY)
+                        .await?
+                        .filter(|t| t.is_active),
+                    TemplateLanguage::English => None,
+                };
+                match imaging_template {
+                    Some(template) => template,
+                    None => self
+                        .get_default_template(DocumentType::ReferralLetter, request.language)
+                        .await?
+                        .ok_or_else(|| {
+                            anyhow::anyhow!(
+                                "No default referral template for language '{}'",
+                                request.language.as_str()
+                            )
+                        })?,
+                }
+            }
+        };
+
+        let (imaging, referral) = imaging_referral_context(order, request);
+        let exam = imaging["exam"].as_str().unwrap_or_default().to_string();
+        let document_title = match request.language {
+            TemplateLanguage::Italian => format!("Richiesta {}", exam),
+            TemplateLanguage::English => format!("Imaging referral: {}", exam),
+        };
+
+        self.generate_document(
+            GenerateDocumentRequest {
+                template_id: template.id,
+                patient_id: order.patient_id,
+                document_title,
+                visit_id: order.visit_id,
+                visit_date: order.visit_date,
+                additional_data: Some(serde_json::json!({
+                    "imaging": imaging,
+                    "referral": referral,
+                })),
+                expires_at: None,
+            },
+            provider_id,
+        )
+        .await
     }
 
-    /// Get document file path for download
-    pub async fn get_document_file_path(&self, id: Uuid, user_id: Uuid) -> Result<Option<String>> {
-        // Start transaction for RLS context
-        let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
+    // ==================== Referral Letters ====================
 
-        // Set RLS context
-        Self::set_rls_context(&mut tx, user_id).await?;
+    /// Generate the letter for a specialist referral
+    ///
+    /// Uses the requested REFERRAL_LETTER template, otherwise the default
+    /// referral template for the language.
+    pub async fn generate_referral_letter(
+        &self,
+        referral: &ReferralResponse,
+        request: &GenerateReferralLetterRequest,
+        provider_id: Uuid,
+    ) -> Result<GeneratedDocumentResponse> {
+        let template = match request.template_id {
+            Some(template_id) => self
+                .get_template(template_id)
+                .await?
+                .filter(|t| t.document_type == DocumentType::ReferralLetter)
+                .ok_or_else(|| anyhow::anyhow!("Referral template {} not found", template_id))?,
+            None => self
+                .get_default_template(DocumentType::ReferralLetter, request.language)
+                .await?
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "No default referral template for language '{}'",
+                        request.language.as_str()
+                    )
+                })?,
+        };
 
-        let result = sqlx::query!(
-            r#"
-            SELECT file_path FROM generated_documents
-            WHERE id = $1 AND status != 'DELETED'
-            "#,
-            id
+        let document_title = match request.language {
+            TemplateLanguage::Italian => format!("Lettera di invio - {}", referral.specialty),
+            TemplateLanguage::English => format!("Referral letter - {}", referral.specialty),
+        };
+
+        self.generate_document(
+            GenerateDocumentRequest {
+                template_id: template.id,
+                patient_id: referral.patient_id,
+                document_title,
+                visit_id: referral.visit_id,
+                visit_date: referral.visit_date,
+                additional_data: Some(serde_json::json!({
+                    "referral": referral_letter_context(referral, request),
+                })),
+                expires_at: None,
+            },
+            provider_id,
         )
-        .fetch_optional(&mut *tx)
         .await
-        .context("Failed to fetch document file path")?;
+    }
 
-        tx.commit().await.context("Failed to commit transaction")?;
+    // ==================== NRE Promemoria ====================
 
-        // Validate path is within storage directory to prevent path traversal
-        if let Some(ref record) = result {
-            let file_path = std::path::Path::new(&record.file_path);
-            let canonical_file = file_path
-                .canonicalize()
-                .context("Failed to resolve document file path")?;
-            let canonical_storage = self
-                .storage_path
-                .canonicalize()
-                .context("Failed to resolve storage path")?;
-            if !canonical_file.starts_with(&canonical_storage) {
-                anyhow::bail!("Document file path is outside the storage directory");
+    /// Generate the promemoria of a dematerialized prescription
+    ///
+    /// Uses the requested PRESCRIPTION template; otherwise the NRE promemoria
+    /// template, falling back to the default prescription template for the
+    /// language. Fills the `nre` variables and the generic `prescription`
+    /// ones, so any prescription template prints the medication.
+    pub async fn generate_nre_promemoria(
+        &self,
+        promemoria: &NrePromemoria,
+        request: &GenerateNrePromemoriaRequest,
+        provider_id: Uuid,
+    ) -> Result<GeneratedDocumentResponse> {
+        let template = match request.template_id {
+            Some(template_id) => self
+                .get_template(template_id)
+                .await?
+                .filter(|t| t.document_type == DocumentType::Prescription)
+                .ok_or_else(|| anyhow::anyhow!("Prescription template {} not found", template_id))?,
+            None => {
+                let promemoria_template = match request.language {
+                    TemplateLanguage::Italian => self
+                        .get_template_by_key(NRE_PROMEMORIA_TEMPLATE_KEY)
+                        .await?
+                        .filter(|t| t.is_active),
+                    TemplateLanguage::English => None,
+                };
+                match promemoria_template {
+                    Some(template) => template,
+                    None => self
+                        .get_default_template(DocumentType::Prescription, request.language)
+                        .await?
+                        .ok_or_else(|| {
+                            anyhow::anyhow!(
+                                "No default prescription template for language '{}'",
+                                request.language.as_str()
+                            )
+                        })?,
+                }
             }
-        }
+        };
 
-        Ok(result.map(|r| r.file_path))
+        let (nre, prescription) = nre_promemoria_context(promemoria);
+        let document_title = match request.language {
+            TemplateLanguage::Italian => format!("Promemoria ricetta {}", promemoria.nre),
+            TemplateLanguage::English => format!("E-prescription {}", promemoria.nre),
+        };
+
+        self.generate_document(
+            GenerateDocumentRequest {
+                template_id: template.id,
+                patient_id: promemoria.prescription.patient_id,
+                document_title,
+                visit_id: promemoria.prescription.visit_id,
+                visit_date: promemoria.prescription.visit_date,
+                additional_data: Some(serde_json::json!({
+                    "nre": nre,
+                    "prescription": prescription,
+                })),
+                expires_at: None,
+            },
+            provider_id,
+        )
+        .await
     }
 
-    /// List generated documents with filtering
-    pub async fn list_documents(
+    // ==================== SSN Prescription ====================
+
+    /// Print a prescription on the SSN "ricetta" layout
+    ///
+    /// Uses the requested SSN_PRESCRIPTION template, otherwise the default
+    /// one. The `ricetta` variables come straight from the prescription and
+    /// the patient's fiscal code; fails if the prescription is for more
+    /// packages than a ricetta allows.
+    pub async fn generate_ssn_prescription(
         &self,
-        filter: GeneratedDocumentFilter,
-        limit: i64,
-        offset: i64,
-        user_id: Uuid,
-    ) -> Result<ListGeneratedDocumentsResponse> {
-        // Start transaction for RLS context
-        let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
+        prescription: &PrescriptionResponse,
+        request: &GenerateSsnPrescriptionRequest,
+        provider_id: Uuid,
+    ) -> Result<GeneratedDocumentResponse> {
+        let template = match request.template_id {
+            Some(template_id) => self
+                .get_template(template_id)
+                .await?
+                .filter(|t| t.document_type == DocumentType::SsnPrescription)
+                .ok_or_else(|| anyhow::anyhow!("SSN prescription template {} not found", template_id))?,
+            None => self
+                .get_default_template(DocumentType::SsnPrescription, TemplateLanguage::Italian)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("No default SSN prescription template"))?,
+        };
 
-        // Set RLS context
-        Self::set_rls_context(&mut tx, user_id).await?;
+        let fiscal_code = self
+            .patient_fiscal_code(prescription.patient_id, provider_id)
+            .await?;
+        let ricetta = ssn_prescription_context(prescription, fiscal_code.as_deref(), request)?;
+
+        self.generate_document(
+            GenerateDocumentRequest {
+                template_id: template.id,
+                patient_id: prescription.patient_id,
+                document_title: format!("Ricetta SSN - {}", prescription.medication_name),
+                visit_id: prescription.visit_id,
+                visit_date: prescription.visit_date,
+                additional_data: Some(serde_json::json!({ "ricetta": ricetta })),
+                expires_at: None,
+            },
+            provider_id,
+        )
+        .await
+    }
 
-        let doc_type_str = filter.document_type.map(|dt| dt.as_str().to_string());
-        let status_str = filter.status.map(|s| s.as_str().to_string());
+    /// Decrypted fiscal code of a patient, if recorded
+    async fn patient_fiscal_code(&self, patient_id: Uuid, user_id: Uuid) -> Result<Option<String>> {
+        let mut tx = begin_with_rls(&self.pool, user_id)
+            .await
+            .context("Failed to begin transaction")?;
+
+        let encrypted: Option<String> =
+            sqlx::query_scalar("SELECT fiscal_code FROM patients WHERE id = $1")
+                .bind(patient_id)
+                .fetch_optional(&mut *tx)
+                .await
+                .context("Failed to fetch patient fiscal code")?
+                .ok_or_else(|| anyhow::anyhow!("Patient {} not found", patient_id))?;
+        tx.commit().await.context("Failed to commit transaction")?;
 
-        let documents = sqlx::query_as!(
-            GeneratedDocument,
+        encrypted
+            .map(|f| self.encryption_key.decrypt(&f))
+            .transpose()
+            .context("Failed to decrypt patient fiscal_code")
+    }
+
+    // ==================== Prescription Print Batches ====================
+
+    /// Record a new prescription print batch in PROCESSING state
+    ///
+    /// `complete_prescription_print_batch` renders the merged PDF, during the
+    /// request for small batches or from a background task.
+    pub async fn create_prescription_print_batch(
+        &self,
+        layout: PrescriptionPrintLayout,
+        prescriptions: &[PrescriptionResponse],
+        requested_by: Uuid,
+    ) -> Result<PrescriptionPrintBatchResponse> {
+        let prescription_ids: Vec<Uuid> = prescriptions.iter().map(|rx| rx.id).collect();
+
+        let batch = sqlx::query_as::<_, PrescriptionPrintBatch>(&format!(
             r#"
-            SELECT
-                id, template_id, patient_id, visit_id, visit_date, provider_id,
-                document_type, document_title, document_filename,
-                file_path, file_size_bytes, file_hash,
-                template_version, generation_data,
-                status, generation_error,
-                delivered_to, delivered_at,
-                expires_at, deleted_at,
-                is_signed, signature_hash, signed_at, signed_by,
-                created_at, updated_at, created_by, updated_by
-            FROM generated_documents
-            WHERE
-                status != 'DELETED'
-                AND ($1::UUID IS NULL OR patient_id = $1)
-                AND ($2::UUID IS NULL OR visit_id = $2)
-                AND ($3::UUID IS NULL OR provider_id = $3)
-                AND ($4::VARCHAR IS NULL OR document_type = $4)
-                AND ($5::VARCHAR IS NULL OR status = $5)
-                AND (($6::BOOLEAN IS NULL) OR (is_signed = $6))
-                AND ($7::TIMESTAMPTZ IS NULL OR created_at >= $7)
-                AND ($8::TIMESTAMPTZ IS NULL OR created_at <= $8)
-            ORDER BY created_at DESC
-            LIMIT $9 OFFSET $10
+            INSERT INTO prescription_print_batches (requested_by, status, layout, prescription_ids)
+            VALUES ($1, $2, $3, $4)
+            RETURNING {}
             "#,
-            filter.patient_id,
-            filter.visit_id,
-            filter.provider_id,
-            doc_type_str,
-            status_str,
-            filter.is_signed as Option<bool>,
-            filter.from_date,
-            filter.to_date,
-            limit,
-            offset
-        )
-        .fetch_all(&mut *tx)
+            PRINT_BATCH_COLUMNS
+        ))
+        .bind(requested_by)
+        .bind(PrintBatchStatus::Processing.as_str())
+        .bind(layout.as_str())
+        .bind(&prescription_ids)
+        .fetch_one(&self.pool)
         .await
-        .context("Failed to list generated documents")?;
+        .context("Failed to create prescription print batch")?;
 
-        // Get count with same filter
-        let doc_type_str_count = filter.document_type.map(|dt| dt.as_str().to_string());
-        let status_str_count = filter.status.map(|s| s.as_str().to_string());
+        Ok(PrescriptionPrintBatchResponse::from(batch))
+    }
 
-        let total = sqlx::query_scalar!(
-            r#"
-            SELECT COUNT(*) as "count!"
-            FROM generated_documents
-            WHERE
-                status != 'DELETED'
-                AND ($1::UUID IS NULL OR patient_id = $1)
-                AND ($2::UUID IS NULL OR visit_id = $2)
-                AND ($3::UUID IS NULL OR provider_id = $3)
-                AND ($4::VARCHAR IS NULL OR document_type = $4)
-                AND ($5::VARCHAR IS NULL OR status = $5)
-                AND (($6::BOOLEAN IS NULL) OR (is_signed = $6))
-                AND ($7::TIMESTAMPTZ IS NULL OR created_at >= $7)
-                AND ($8::TIMESTAMPTZ IS NULL OR created_at <= $8)
-            "#,
-            filter.patient_id,
-            filter.visit_id,
-            filter.provider_id,
-            doc_type_str_count,
-            status_str_count,
-            filter.is_signed as Option<bool>,
-            filter.from_date,
-            filter.to_date
-        )
-        .fetch_one(&mut *tx)
+    /// Render a print batch and move it to COMPLETED
+    ///
+    /// On error the batch is moved to FAILED with the error message.
+    pub async fn complete_prescription_print_batch(
+        &self,
+        batch_id: Uuid,
+        layout: PrescriptionPrintLayout,
+        prescriptions: &[PrescriptionResponse],
+        requested_by: Uuid,
+    ) -> Result<PrescriptionPrintBatchResponse> {
+        let rendered = self
+            .render_prescription_batch(layout, prescriptions, requested_by)
+            .await;
+
+        let updated = match &rendered {
+            Ok(stored) => sqlx::query_as::<_, PrescriptionPrintBatch>(&format!(
+                r#"
+                UPDATE prescription_print_batches
+                SET status = $2,
+                    file_path = $3,
+                    file_size_bytes = $4,
+                    file_hash = $5,
+                    completed_at = NOW()
+                WHERE id = $1 AND status = 'PROCESSING'
+                RETURNING {}
+                "#,
+                PRINT_BATCH_COLUMNS
+            ))
+            .bind(batch_id)
+            .bind(PrintBatchStatus::Completed.as_str())
+            .bind(&stored.file_path)
+            .bind(stored.file_size_bytes)
+            .bind(&stored.file_hash)
+            .fetch_one(&self.pool)
+            .await
+            .context("Failed to update prescription print batch")?,
+            Err(e) => sqlx::query_as::<_, PrescriptionPrintBatch>(&format!(
+                r#"
+                UPDATE prescription_print_batches
+                SET status = $2, error_message = $3, completed_at = NOW()
+                WHERE id = $1 AND status = 'PROCESSING'
+                RETURNING {}
+                "#,
+                PRINT_BATCH_COLUMNS
+            ))
+            .bind(batch_id)
+            .bind(PrintBatchStatus::Failed.as_str())
+            .bind(format!("{:#}", e))
+            .fetch_one(&self.pool)
+            .await
+            .context("Failed to mark prescription print batch as failed")?,
+        };
+
+        rendered?;
+
+        Ok(PrescriptionPrintBatchResponse::from(updated))
+    }
+
+    /// Get a print batch requested by the user
+    pub async fn get_prescription_print_batch(
+        &self,
+        batch_id: Uuid,
+        requested_by: Uuid,
+    ) -> Result<Option<PrescriptionPrintBatch>> {
+        sqlx::query_as::<_, PrescriptionPrintBatch>(&format!(
+            "SELECT {} FROM prescription_print_batches WHERE id = $1 AND requested_by = $2",
+            PRINT_BATCH_COLUMNS
+        ))
+        .bind(batch_id)
+        .bind(requested_by)
+        .fetch_optional(&self.pool)
         .await
-        .context("Failed to count documents")?;
+        .context("Failed to fetch prescription print batch")
+    }
 
-        tx.commit().await.context("Failed to commit transaction")?;
+    /// Render every prescription with the layout's default template, one
+    /// per page, into a single PDF
+    async fn render_prescription_batch(
+        &self,
+        layout: PrescriptionPrintLayout,
+        prescriptions: &[PrescriptionResponse],
+        requested_by: Uuid,
+    ) -> Result<StoredPdf> {
+        let document_type = match layout {
+            PrescriptionPrintLayout::Standard => DocumentType::Prescription,
+            PrescriptionPrintLayout::Ssn => DocumentType::SsnPrescription,
+        };
+        let template = self
+            .get_default_template(document_type, TemplateLanguage::Italian)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("No default {} template", document_type.as_str()))?;
+
+        let mut contexts: HashMap<Uuid, GenerationContext> = HashMap::new();
+        let mut pages = Vec::with_capacity(prescriptions.len());
+        for rx in prescriptions {
+            if !contexts.contains_key(&rx.patient_id) {
+                let context = self.load_generation_context(rx.patient_id, requested_by).await?;
+                contexts.insert(rx.patient_id, context);
+            }
+            let GenerationContext { patient, provider, clinic } = &contexts[&rx.patient_id];
+
+            let mut variables = serde_json::json!({
+                "patient": patient,
+                "provider": provider,
+                "clinic": clinic,
+                "document": {
+                    "date": Utc::now().format("%d/%m/%Y").to_string(),
+                },
+            });
+            match layout {
+                PrescriptionPrintLayout::Standard => {
+                    variables["prescription"] = prescription_context(rx);
+                }
+                PrescriptionPrintLayout::Ssn => {
+                    let fiscal_code = patient["fiscal_code"].as_str().filter(|c| *c != "none");
+                    variables["ricetta"] = ssn_prescription_context(rx, fiscal_code, &Default::default())
+                        .with_context(|| format!("Prescription {}", rx.id))?;
+                }
+            }
 
-        Ok(ListGeneratedDocumentsResponse {
-            documents: documents
-                .into_iter()
-                .map(GeneratedDocumentSummary::from)
-                .collect(),
-            total,
-            limit,
-            offset,
+            // Header and footer are printed on every page
+            let sections = [
+                template.header_html.as_deref(),
+                Some(template.template_html.as_str()),
+                template.footer_html.as_deref(),
+            ];
+            let mut page = String::new();
+            for html in sections.into_iter().flatten() {
+                page.push_str(
+                    &self
+                        .substitute_variables(html, &variables)
+                        .with_context(|| format!("Failed to render prescription {}", rx.id))?,
+                );
+            }
+            pages.push(page);
+        }
+
+        let pdf_bytes = self.render_pdf_from_html(
+            &pages.join(PAGE_BREAK_MARKER),
+            None,
+            None,
+            template.css_styles.as_deref(),
+            template.page_size,
+            template.page_orientation,
+            template.margin_top_mm,
+            template.margin_bottom_mm,
+            template.margin_left_mm,
+            template.margin_right_mm,
+        ).context("Failed to render PDF from HTML")?;
+
+        let file_hash = self.calculate_hash(&pdf_bytes);
+        let filename = format!("prescription_batch_{}.pdf", Utc::now().format("%Y%m%d_%H%M%S_%3f"));
+        let file_path = self.store_file(&filename, &pdf_bytes).await
+            .context("Failed to store PDF file")?;
+
+        Ok(StoredPdf {
+            filename,
+            file_path,
+            file_size_bytes: pdf_bytes.len() as i64,
+            file_hash,
         })
     }
 
-    /// Mark document as delivered
-    pub async fn deliver_document(
+    // ==================== Patient Chart Print Bundle ====================
+
+    /// Queue a patient chart print bundle
+    ///
+    /// Creates the document in GENERATING state and returns it straight away;
+    /// `complete_print_bundle` renders the PDF (normally from a background task).
+    /// Committed on its own, so the task sees the document before the request ends.
+    /// Returns None if the patient does not exist or is not visible to the user.
+    pub async fn create_print_bundle(
         &self,
-        id: Uuid,
-        data: DeliverDocumentRequest,
-        delivered_by: Uuid,
-    ) -> Result<GeneratedDocumentResponse> {
-        // Start transaction for RLS context and update
-        let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
+        patient_id: Uuid,
+        request: &CreatePrintBundleRequest,
+        provider_id: Uuid,
+    ) -> Result<Option<GeneratedDocumentResponse>> {
+        let template = self
+            .get_template_by_key(PRINT_BUNDLE_TEMPLATE_KEY)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Print bundle template '{}' not found", PRINT_BUNDLE_TEMPLATE_KEY))?;
 
-        // Set RLS context
-        Self::set_rls_context(&mut tx, delivered_by).await?;
+        let mut tx = begin_detached_with_rls(&self.pool, provider_id)
+            .await
+            .context("Failed to begin transaction")?;
 
-        let document = sqlx::query_as!(
-            GeneratedDocument,
+        let patient_exists: bool =
+            sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM patients WHERE id = $1)")
+                .bind(patient_id)
+                .fetch_one(&mut *tx)
+                .await
+                .context("Failed to check patient")?;
+
+        if !patient_exists {
+            return Ok(None);
+        }
+
+        let title = request
+            .document_title
+            .clone()
+            .unwrap_or_else(|| format!("Estratto cartella clinica {}", Utc::now().format("%d/%m/%Y")));
+
+        // file_path is filled in once the PDF has been rendered; document_filename
+        // is assigned by the generate_document_filename trigger
+        let document = sqlx::query_as::<_, GeneratedDocument>(
             r#"
-            UPDATE generated_documents
-            SET
-                status = 'DELIVERED',
-                delivered_to = $2,
-                delivered_at = NOW(),
-                updated_by = $3,
-                updated_at = NOW()
-            WHERE id = $1 AND status = 'GENERATED'
+            INSERT INTO generated_documents (
+                template_id, patient_id, provider_id,
+                document_type, document_title, document_filename, file_path,
+                template_version, status, generation_request, created_by
+            )
+            VALUES ($1, $2, $3, $4, $5, '', '', $6, $7, $8, $3)
             RETURNING
                 id, template_id, patient_id, visit_id, visit_date, provider_id,
                 document_type, document_title, document_filename,
@@ -1047,232 +1596,441 @@ impl DocumentService {
                 is_signed, signature_hash, signed_at, signed_by,
                 created_at, updated_at, created_by, updated_by
             "#,
-            id,
-            data.delivered_to,
-            delivered_by
         )
+        .bind(template.id)
+        .bind(patient_id)
+        .bind(provider_id)
+        .bind(template.document_type.as_str())
+        .bind(title)
+        .bind(template.version)
+        .bind(DocumentStatus::Generating.as_str())
+        .bind(self.encrypt_generation_request(&GenerationRequest::PrintBundle(request.clone()))?)
         .fetch_one(&mut *tx)
         .await
-        .context("Failed to mark document as delivered")?;
+        .context("Failed to create print bundle document")?;
 
         tx.commit().await.context("Failed to commit transaction")?;
 
-        Ok(GeneratedDocumentResponse::from(document))
+        Ok(Some(GeneratedDocumentResponse::from(document)))
     }
 
-    /// Sign document digitally with visible signature block
-    pub async fn sign_document(
+    /// Render a queued print bundle and move it to GENERATED
+    ///
+    /// On error the document is moved to FAILED with the error message.
+    pub async fn complete_print_bundle(
         &self,
-        id: Uuid,
-        signed_by: Uuid,
-    ) -> Result<GeneratedDocumentResponse> {
-        // Start transaction for RLS context and update
-        let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-
-        // Set RLS context
-        Self::set_rls_context(&mut tx, signed_by).await?;
+        document_id: Uuid,
+        patient_id: Uuid,
+        request: &CreatePrintBundleRequest,
+        provider_id: Uuid,
+    ) -> Result<()> {
+        let rendered = self
+            .render_print_bundle(patient_id, request, provider_id)
+            .await;
 
-        // Get current document
-        let doc = sqlx::query!(
-            r#"
-            SELECT
-                file_hash, document_title, patient_id, created_at,
-                is_signed, template_id, generation_data, file_path
-            FROM generated_documents
-            WHERE id = $1
-            "#,
-            id
-        )
-        .fetch_one(&mut *tx)
-        .await
-        .context("Failed to fetch document for signing")?;
+        let mut tx = begin_with_rls(&self.pool, provider_id)
+            .await
+            .context("Failed to begin transaction")?;
+
+        let result = match rendered {
+            Ok((stored, variables)) => {
+                let encrypted_variables = self
+                    .encryption_key
+                    .encrypt_json(&variables)
+                    .context("Failed to encrypt generation data")?;
+
+                sqlx::query(
+                    r#"
+                    UPDATE generated_documents
+                    SET status = $2,
+                        document_filename = $3,
+                        file_path = $4,
+                        file_size_bytes = $5,
+                        file_hash = $6,
+                        generation_data = $7,
+                        updated_by = $8
+                    WHERE id = $1 AND status = 'GENERATING'
+                    "#,
+                )
+                .bind(document_id)
+                .bind(DocumentStatus::Generated.as_str())
+                .bind(&stored.filename)
+                .bind(&stored.file_path)
+                .bind(stored.file_size_bytes)
+                .bind(&stored.file_hash)
+                .bind(serde_json::json!({"encrypted": encrypted_variables}))
+                .bind(provider_id)
+                .execute(&mut *tx)
+                .await
+                .context("Failed to update print bundle document")?;
+
+                Ok(())
+            }
+            Err(e) => {
+                sqlx::query(
+                    r#"
+                    UPDATE generated_documents
+                    SET status = $2, generation_error = $3, updated_by = $4
+                    WHERE id = $1 AND status = 'GENERATING'
+                    "#,
+                )
+                .bind(document_id)
+                .bind(DocumentStatus::Failed.as_str())
+                .bind(format!("{:#}", e))
+                .bind(provider_id)
+                .execute(&mut *tx)
+                .await
+                .context("Failed to mark print bundle as failed")?;
+
+                Err(e)
+            }
+        };
 
-        // Get template info
-        let template = sqlx::query!(
-            r#"
-            SELECT
-                template_html, header_html, footer_html, css_styles,
-                page_size, page_orientation,
-                margin_top_mm, margin_bottom_mm, margin_left_mm, margin_right_mm
-            FROM document_templates
-            WHERE id = $1
-            "#,
-            doc.template_id
-        )
-        .fetch_optional(&mut *tx)
-        .await
-        .context("Failed to fetch template for signing")?;
+        tx.commit().await.context("Failed to commit transaction")?;
 
-        // Validate file path is within storage directory (path traversal prevention)
-        {
-            let file_path = std::path::Path::new(&doc.file_path);
-            let canonical_file = file_path
-                .canonicalize()
-                .context("Failed to resolve document file path")?;
-            let canonical_storage = self
-                .storage_path
-                .canonicalize()
-                .context("Failed to resolve storage path")?;
-            if !canonical_file.starts_with(&canonical_storage) {
-                anyhow::bail!("Document file path is outside the storage directory");
-            }
-        }
+        result
+    }
 
-        // Prevent double-signing
-        if doc.is_signed.unwrap_or(false) {
-            anyhow::bail!("Document is already signed");
-        }
+    /// Collect the requested chart sections and render the bundle PDF
+    async fn render_print_bundle(
+        &self,
+        patient_id: Uuid,
+        request: &CreatePrintBundleRequest,
+        provider_id: Uuid,
+    ) -> Result<(StoredPdf, serde_json::Value)> {
+        let template = self
+            .get_template_by_key(PRINT_BUNDLE_TEMPLATE_KEY)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Print bundle template '{}' not found", PRINT_BUNDLE_TEMPLATE_KEY))?;
+
+        let GenerationContext { patient, provider, clinic } =
+            self.load_generation_context(patient_id, provider_id).await?;
+        let bundle = self
+            .load_bundle_sections(patient_id, request, provider_id)
+            .await?;
+
+        let variables = serde_json::json!({
+            "patient": patient,
+            "provider": provider,
+            "clinic": clinic,
+            "document": {
+                "date": Utc::now().format("%d/%m/%Y").to_string(),
+            },
+            "bundle": bundle,
+        });
 
-        // Fetch signer information
-        let signer = sqlx::query!(
-            r#"
-            SELECT first_name, last_name, role, email
-            FROM users WHERE id = $1
-            "#,
-            signed_by
-        )
-        .fetch_one(&mut *tx)
-        .await
-        .context("Failed to fetch signer information")?;
+        let stored = self.render_and_store(&template, &variables).await?;
 
-        let signer_name = format!("{} {}", signer.first_name, signer.last_name);
-        let signed_at = Utc::now();
+        Ok((stored, variables))
+    }
 
-        // Create signature hash (SHA-256 of file_hash + signer + timestamp)
-        let signature_content = format!(
-            "{}:{}:{}:{}",
-            doc.file_hash.clone().unwrap_or_default(),
-            signed_by,
-            doc.patient_id,
-            signed_at.timestamp()
-        );
-        let signature_hash = self.calculate_hash(signature_content.as_bytes());
+    /// Load the chart data for the requested sections
+    async fn load_bundle_sections(
+        &self,
+        patient_id: Uuid,
+        request: &CreatePrintBundleRequest,
+        provider_id: Uuid,
+    ) -> Result<serde_json::Value> {
+        let sections = request.resolved_sections();
+
+        // Demographics are always loaded: the bundle header shows the MRN
+        let patient = PatientService::new(self.pool.clone(), self.encryption_key.clone())
+            .get_patient(patient_id, Some(provider_id), None)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Patient not found"))?;
+
+        let demographics = demographics_context(patient);
+
+        let problems = if sections.contains(&ChartSection::ActiveProblems) {
+            let diagnoses = VisitDiagnosisService::new(self.pool.clone(), self.encryption_key.clone())
+                .get_patient_diagnoses(patient_id, true)
+                .await?;
+            active_problems(&diagnoses)
+        } else {
+            Vec::new()
+        };
 
-        // Regenerate PDF with signature block
-        let new_pdf_bytes = if let Some(ref tpl) = template {
-            // Decrypt generation data to get original variables
-            let variables: serde_json::Value = if let Some(ref gen_data) = doc.generation_data {
-                if let Some(encrypted) = gen_data.get("encrypted").and_then(|v: &serde_json::Value| v.as_str()) {
-                    self.encryption_key.decrypt_json(encrypted)
-                        .unwrap_or_else(|_| serde_json::json!({}))
-                } else {
-                    serde_json::json!({})
-                }
-            } else {
-                serde_json::json!({})
-            };
+        let visits = if sections.contains(&ChartSection::RecentVisits) {
+            // Attachments are listed by name: the PDF renderer prints text only
+            let mut attachments: HashMap<Uuid, Vec<serde_json::Value>> = HashMap::new();
+            for attachment in VisitAttachmentService::new(self.pool.clone(), self.encryption_key.clone())
+                .list_patient_attachments(patient_id, provider_id)
+                .await?
+            {
+                attachments
+                    .entry(attachment.visit_id)
+                    .or_default()
+                    .push(serde_json::json!({
+                        "kind": attachment.kind,
+                        "caption": attachment.caption,
+                        "body_site": attachment.body_site,
+                        "filename": attachment.original_filename,
+                    }));
+            }
 
-            // Render the original content with variables
-            let rendered_html = self.substitute_variables(&tpl.template_html, &variables)
-                .context("Failed to substitute template variables")?;
+            VisitService::new(self.pool.clone(), self.encryption_key.clone())
+                .get_patient_visits(patient_id, provider_id, Some(request.resolved_visit_limit()), None)
+                .await?
+                .into_iter()
+                .map(|visit| {
+                    let provider = match (&visit.provider_first_name, &visit.provider_last_name) {
+                        (Some(first), Some(last)) => format!("Dr. {} {}", first, last),
+                        _ => String::new(),
+                    };
+                    serde_json::json!({
+                        "date": visit.visit_date.format("%d/%m/%Y").to_string(),
+                        "type": visit.visit_type,
+                        "provider": provider,
+                        "chief_complaint": visit.chief_complaint,
+                        "vitals": visit.vitals,
+                        "assessment": visit.assessment,
+                        "plan": visit.plan,
+                        "attachments": attachments.remove(&visit.id).unwrap_or_default(),
+                    })
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
 
-            // Create signature block HTML
-            let signature_block = format!(
-                r#"
-                <div style="margin-top: 40px; padding-top: 20px; border-top: 2px solid #333;">
-                    <div style="text-align: center; margin-bottom: 20px;">
-                        <strong>═══════════════════════════════════════════════════════════</strong>
-                    </div>
-                    <div style="text-align: center; margin-bottom: 15px;">
-                        <strong>DIGITALLY SIGNED DOCUMENT</strong>
-                    </div>
-                    <div style="margin: 10px 0;">
-                        <strong>Signed by:</strong> Dr. {}
-                    </div>
-                    <div style="margin: 10px 0;">
-                        <strong>Role:</strong> {}
-                    </div>
-                    <div style="margin: 10px 0;">
-                        <strong>Date and Time:</strong> {}
-                    </div>
-                    <div style="margin: 10px 0;">
-                        <strong>Digital Signature Hash:</strong>
-                    </div>
-                    <div style="font-family: monospace; font-size: 10px; word-break: break-all; margin: 5px 0;">
-                        {}
-                    </div>
-                    <div style="text-align: center; margin-top: 20px;">
-                        <strong>═══════════════════════════════════════════════════════════</strong>
-                    </div>
-                    <div style="text-align: center; font-size: 10px; margin-top: 10px; color: #666;">
-                        This document has been digitally signed and any modifications will invalidate the signature.
-                    </div>
-                </div>
-                "#,
-                signer_name,
-                signer.role,
-                signed_at.format("%Y-%m-%d %H:%M:%S UTC"),
-                signature_hash
-            );
+        let medications = if sections.contains(&ChartSection::CurrentMedications) {
+            let role = self.user_role(provider_id).await?;
 
-            // Combine original content with signature block
-            let signed_content = format!("{}\n{}", rendered_html, signature_block);
+            PrescriptionService::new(self.pool.clone(), self.encryption_key.clone())
+                .get_patient_prescriptions(patient_id, true, provider_id, &role)
+                .await?
+                .into_iter()
+                .map(|rx| {
+                    serde_json::json!({
+                        "name": rx.medication_name,
+                        "generic_name": rx.generic_name,
+                        "dosage": rx.dosage,
+                        "frequency": rx.frequency,
+                        "route": rx.route,
+                        "instructions": rx.instructions,
+                        "start_date": rx.start_date.unwrap_or(rx.prescribed_date).format("%d/%m/%Y").to_string(),
+                    })
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
 
-            // Render header/footer with variables
-            let rendered_header = tpl.header_html
-                .as_ref()
-                .map(|h| self.substitute_variables(h, &variables))
-                .transpose()
-                .context("Failed to substitute header variables")?;
-            let rendered_footer = tpl.footer_html
-                .as_ref()
-                .map(|f| self.substitute_variables(f, &variables))
-                .transpose()
-                .context("Failed to substitute footer variables")?;
+        // Lab results are not recorded in the chart yet, so the section is
+        // always printed as empty
+        let labs: Vec<serde_json::Value> = Vec::new();
+
+        Ok(serde_json::json!({
+            "sections": sections.iter().map(|s| s.as_str()).collect::<Vec<_>>(),
+            "purpose": request.purpose,
+            "demographics": demographics,
+            "problems": problems,
+            "visits": visits,
+            "medications": medications,
+            "labs": labs,
+        }))
+    }
 
-            // Parse page size and orientation
-            let page_size = tpl.page_size
-                .as_deref()
-                .map(PageSize::from_str)
-                .unwrap_or(PageSize::A4);
-            let orientation = tpl.page_orientation
-                .as_deref()
-                .map(PageOrientation::from_str)
-                .unwrap_or(PageOrientation::Portrait);
+    // ==================== Clinical Summary ====================
 
-            // Generate new PDF with signature
-            self.render_pdf_from_html(
-                &signed_content,
-                rendered_header.as_deref(),
-                rendered_footer.as_deref(),
-                tpl.css_styles.as_deref(),
-                page_size,
-                orientation,
-                tpl.margin_top_mm.unwrap_or(20),
-                tpl.margin_bottom_mm.unwrap_or(20),
-                tpl.margin_left_mm.unwrap_or(20),
-                tpl.margin_right_mm.unwrap_or(20),
-            ).context("Failed to render signed PDF")?
-        } else {
-            // No template body available, keep original file
-            tokio::fs::read(&doc.file_path).await
-                .context("Failed to read original PDF file")?
+    /// Render the clinical summary of a visit
+    ///
+    /// Demographics, vitals, SOAP notes, diagnoses and prescriptions of the
+    /// visit, on the built-in clinical summary template: no template or
+    /// variables to pick. Returns None if the visit does not exist or is not
+    /// visible to the user.
+    pub async fn generate_visit_summary(
+        &self,
+        visit_id: Uuid,
+        provider_id: Uuid,
+    ) -> Result<Option<GeneratedDocumentResponse>> {
+        let template = self.clinical_summary_template().await?;
+
+        let Some(visit) = VisitService::new(self.pool.clone(), self.encryption_key.clone())
+            .get_visit(visit_id, provider_id, None)
+            .await?
+        else {
+            return Ok(None);
         };
 
-        // Calculate new file hash
-        let new_file_hash = self.calculate_hash(&new_pdf_bytes);
-        let new_file_size = new_pdf_bytes.len() as i64;
+        let role = self.user_role(provider_id).await?;
+        let diagnoses = VisitDiagnosisService::new(self.pool.clone(), self.encryption_key.clone())
+            .get_visit_diagnoses(visit_id)
+            .await?;
+        let prescriptions = PrescriptionService::new(self.pool.clone(), self.encryption_key.clone())
+            .get_visit_prescriptions(visit_id, provider_id, &role)
+            .await?;
+
+        let summary = serde_json::json!({
+            "scope": "VISIT",
+            "demographics": self.summary_demographics(visit.patient_id, provider_id).await?,
+            "problems": [],
+            "medications": [],
+            "visits": [summary_visit_context(&visit, &diagnoses, &prescriptions)],
+        });
 
-        // Update the stored file
-        tokio::fs::write(&doc.file_path, &new_pdf_bytes).await
-            .context("Failed to update signed PDF file")?;
+        let document = self
+            .generate_document(
+                GenerateDocumentRequest {
+                    template_id: template.id,
+                    patient_id: visit.patient_id,
+                    document_title: format!(
+                        "Riepilogo visita del {}",
+                        visit.visit_date.format("%d/%m/%Y")
+                    ),
+                    visit_id: Some(visit.id),
+                    visit_date: Some(visit.visit_date),
+                    additional_data: Some(serde_json::json!({ "summary": summary })),
+                    expires_at: None,
+                },
+                provider_id,
+            )
+            .await?;
+
+        Ok(Some(document))
+    }
+
+    /// Render the full chart of a patient
+    ///
+    /// Every visit (newest first) with its vitals, SOAP notes, diagnoses and
+    /// prescriptions, preceded by the active problems and current
+    /// medications. Returns None if the patient does not exist or is not
+    /// visible to the user.
+    pub async fn generate_chart_summary(
+        &self,
+        patient_id: Uuid,
+        provider_id: Uuid,
+    ) -> Result<Option<GeneratedDocumentResponse>> {
+        let template = self.clinical_summary_template().await?;
+
+        let Some(patient) = PatientService::new(self.pool.clone(), self.encryption_key.clone())
+            .get_patient(patient_id, Some(provider_id), None)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        let visit_service = VisitService::new(self.pool.clone(), self.encryption_key.clone());
+        let mut visits = Vec::new();
+        loop {
+            let page = visit_service
+                .get_patient_visits(
+                    patient_id,
+                    provider_id,
+                    Some(SUMMARY_VISIT_PAGE_SIZE),
+                    Some(visits.len() as i64),
+                )
+                .await?;
+            let last_page = (page.len() as i64) < SUMMARY_VISIT_PAGE_SIZE;
+            visits.extend(page);
+            if last_page {
+                break;
+            }
+        }
+
+        let role = self.user_role(provider_id).await?;
+        let diagnoses = VisitDiagnosisService::new(self.pool.clone(), self.encryption_key.clone())
+            .get_patient_diagnoses(patient_id, false)
+            .await?;
+        let prescriptions = PrescriptionService::new(self.pool.clone(), self.encryption_key.clone())
+            .get_patient_prescriptions(patient_id, false, provider_id, &role)
+            .await?;
+
+        let medications: Vec<serde_json::Value> = prescriptions
+            .iter()
+            .filter(|rx| rx.status == PrescriptionStatus::Active)
+            .map(summary_prescription_context)
+            .collect();
+        let visits: Vec<serde_json::Value> = visits
+            .iter()
+            .map(|visit| {
+                let visit_diagnoses: Vec<VisitDiagnosisResponse> = diagnoses
+                    .iter()
+                    .filter(|d| d.visit_id == visit.id)
+                    .cloned()
+                    .collect();
+                let visit_prescriptions: Vec<PrescriptionResponse> = prescriptions
+                    .iter()
+                    .filter(|rx| rx.visit_id == Some(visit.id))
+                    .cloned()
+                    .collect();
+                summary_visit_context(visit, &visit_diagnoses, &visit_prescriptions)
+            })
+            .collect();
+
+        let summary = serde_json::json!({
+            "scope": "CHART",
+            "demographics": demographics_context(patient),
+            "problems": active_problems(&diagnoses),
+            "medications": medications,
+            "visits": visits,
+        });
+
+        let document = self
+            .generate_document(
+                GenerateDocumentRequest {
+                    template_id: template.id,
+                    patient_id,
+                    document_title: format!(
+                        "Cartella clinica completa {}",
+                        Utc::now().format("%d/%m/%Y")
+                    ),
+                    visit_id: None,
+                    visit_date: None,
+                    additional_data: Some(serde_json::json!({ "summary": summary })),
+                    expires_at: None,
+                },
+                provider_id,
+            )
+            .await?;
+
+        Ok(Some(document))
+    }
+
+    async fn clinical_summary_template(&self) -> Result<DocumentTemplateResponse> {
+        self.get_template_by_key(CLINICAL_SUMMARY_TEMPLATE_KEY)
+            .await?
+            .filter(|t| t.is_active)
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Clinical summary template '{}' not found",
+                    CLINICAL_SUMMARY_TEMPLATE_KEY
+                )
+            })
+    }
+
+    async fn summary_demographics(
+        &self,
+        patient_id: Uuid,
+        provider_id: Uuid,
+    ) -> Result<serde_json::Value> {
+        let patient = PatientService::new(self.pool.clone(), self.encryption_key.clone())
+            .get_patient(patient_id, Some(provider_id), None)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Patient not found"))?;
+        Ok(demographics_context(patient))
+    }
+
+    /// Role of a user, for the services that apply it to RLS
+    async fn user_role(&self, user_id: Uuid) -> Result<String> {
+        sqlx::query_scalar("SELECT role::TEXT FROM users WHERE id = $1")
+            .bind(user_id)
+            .fetch_one(&self.pool)
+            .await
+            .context("Failed to fetch user role")
+    }
+
+    /// Get generated document by ID
+    pub async fn get_document(&self, id: Uuid, user_id: Uuid) -> Result<Option<GeneratedDocumentResponse>> {
+        // Start transaction for RLS context
+        let mut tx = begin_with_rls(&self.pool, user_id)
+            .await
+            .context("Failed to begin transaction")?;
 
-        // Update database with signature info and new file hash
         let document = sqlx::query_as!(
             GeneratedDocument,
             r#"
-            UPDATE generated_documents
-            SET
-                is_signed = true,
-                signature_hash = $2,
-                signed_at = $3,
-                signed_by = $4,
-                file_hash = $5,
-                file_size_bytes = $6,
-                updated_by = $4,
-                updated_at = NOW()
-            WHERE id = $1
-            RETURNING
+            SELECT
                 id, template_id, patient_id, visit_id, visit_date, provider_id,
                 document_type, document_title, document_filename,
                 file_path, file_size_bytes, file_hash,
@@ -1282,151 +2040,875 @@ impl DocumentService {
                 expires_at, deleted_at,
                 is_signed, signature_hash, signed_at, signed_by,
                 created_at, updated_at, created_by, updated_by
+            FROM generated_documents
+            WHERE id = $1 AND status != 'DELETED'
             "#,
-            id,
-            signature_hash,
-            signed_at,
-            signed_by,
-            new_file_hash,
-            new_file_size
+            id
         )
-        .fetch_one(&mut *tx)
+        .fetch_optional(&mut *tx)
         .await
-        .context("Failed to sign document")?;
+        .context("Failed to fetch generated document")?;
 
         tx.commit().await.context("Failed to commit transaction")?;
 
-        Ok(GeneratedDocumentResponse::from(document))
+        Ok(document.map(GeneratedDocumentResponse::from))
     }
 
-    /// Delete document (soft delete)
-    pub async fn delete_document(&self, id: Uuid, deleted_by: Uuid) -> Result<()> {
-        // Start transaction for RLS context and update
-        let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-
-        // Set RLS context
-        Self::set_rls_context(&mut tx, deleted_by).await?;
+    /// Get document file path for download
+    pub async fn get_document_file_path(&self, id: Uuid, user_id: Uuid) -> Result<Option<String>> {
+        // Start transaction for RLS context
+        let mut tx = begin_with_rls(&self.pool, user_id)
+            .await
+            .context("Failed to begin transaction")?;
 
-        // Check if document is signed - signed documents cannot be deleted
-        let is_signed = sqlx::query_scalar!(
-            r#"SELECT is_signed FROM generated_documents WHERE id = $1"#,
+        let result = sqlx::query!(
+            r#"
+            SELECT file_path FROM generated_documents
+            WHERE id = $1 AND status != 'DELETED'
+            "#,
             id
         )
         .fetch_optional(&mut *tx)
         .await
-        .context("Failed to check document signing status")?;
+        .context("Failed to fetch document file path")?;
 
-        if let Some(signed) = is_signed {
-            if signed.unwrap_or(false) {
-                anyhow::bail!("Signed documents cannot be deleted");
+        tx.commit().await.context("Failed to commit transaction")?;
+
+        // Validate path is within storage directory to prevent path traversal
+        if let Some(ref record) = result {
+            let file_path = std::path::Path::new(&record.file_path);
+            let canonical_file = file_path
+                .canonicalize()
+                .context("Failed to resolve document file path")?;
+            let canonical_storage = self
+                .storage_path
+                .canonicalize()
+                .context("Failed to resolve storage path")?;
+            if !canonical_file.starts_with(&canonical_storage) {
+                anyhow::bail!("Document file path is outside the storage directory");
+            }
+        }
+
+        Ok(result.map(|r| r.file_path))
+    }
+
+    /// List generated documents with filtering
+    pub async fn list_documents(
+        &self,
+        filter: GeneratedDocumentFilter,
+        limit: i64,
+        offset: i64,
+        user_id: Uuid,
+    ) -> Result<ListGeneratedDocumentsResponse> {
+        // Start transaction for RLS context
+        let mut tx = begin_with_rls(&self.pool, user_id)
+            .await
+            .context("Failed to begin transaction")?;
+
+        let doc_type_str = filter.document_type.map(|dt| dt.as_str().to_string());
+        let status_str = filter.status.map(|s| s.as_str().to_string());
+
+        let documents = sqlx::query_as!(
+            GeneratedDocument,
+            r#"
+            SELECT
+                id, template_id, patient_id, visit_id, visit_date, provider_id,
+                document_type, document_title, document_filename,
+                file_path, file_size_bytes, file_hash,
+                template_version, generation_data,
+                status, generation_error,
+                delivered_to, delivered_at,
+                expires_at, deleted_at,
+                is_signed, signature_hash, signed_at, signed_by,
+                created_at, updated_at, created_by, updated_by
+            FROM generated_documents
+            WHERE
+                status != 'DELETED'
+                AND ($1::UUID IS NULL OR patient_id = $1)
+                AND ($2::UUID IS NULL OR visit_id = $2)
+                AND ($3::UUID IS NULL OR provider_id = $3)
+                AND ($4::VARCHAR IS NULL OR document_type = $4)
+                AND ($5::VARCHAR IS NULL OR status = $5)
+                AND (($6::BOOLEAN IS NULL) OR (is_signed = $6))
+                AND ($7::TIMESTAMPTZ IS NULL OR created_at >= $7)
+                AND ($8::TIMESTAMPTZ IS NULL OR created_at <= $8)
+            ORDER BY created_at DESC
+            LIMIT $9 OFFSET $10
+            "#,
+            filter.patient_id,
+            filter.visit_id,
+            filter.provider_id,
+            doc_type_str,
+            status_str,
+            filter.is_signed as Option<bool>,
+            filter.from_date,
+            filter.to_date,
+            limit,
+            offset
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .context("Failed to list generated documents")?;
+
+        // Get count with same filter
+        let doc_type_str_count = filter.document_type.map(|dt| dt.as_str().to_string());
+        let status_str_count = filter.status.map(|s| s.as_str().to_string());
+
+        let total = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) as "count!"
+            FROM generated_documents
+            WHERE
+                status != 'DELETED'
+                AND ($1::UUID IS NULL OR patient_id = $1)
+                AND ($2::UUID IS NULL OR visit_id = $2)
+                AND ($3::UUID IS NULL OR provider_id = $3)
+                AND ($4::VARCHAR IS NULL OR document_type = $4)
+                AND ($5::VARCHAR IS NULL OR status = $5)
+                AND (($6::BOOLEAN IS NULL) OR (is_signed = $6))
+                AND ($7::TIMESTAMPTZ IS NULL OR created_at >= $7)
+                AND ($8::TIMESTAMPTZ IS NULL OR created_at <= $8)
+            "#,
+            filter.patient_id,
+            filter.visit_id,
+            filter.provider_id,
+            doc_type_str_count,
+            status_str_count,
+            filter.is_signed as Option<bool>,
+            filter.from_date,
+            filter.to_date
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .context("Failed to count documents")?;
+
+        tx.commit().await.context("Failed to commit transaction")?;
+
+        Ok(ListGeneratedDocumentsResponse {
+            documents: documents
+                .into_iter()
+                .map(GeneratedDocumentSummary::from)
+                .collect(),
+            total,
+            limit,
+            offset,
+        })
+    }
+
+    /// Mark document as delivered
+    pub async fn deliver_document(
+        &self,
+        id: Uuid,
+        data: DeliverDocumentRequest,
+        delivered_by: Uuid,
+    ) -> Result<GeneratedDocumentResponse> {
+        // Start transaction for RLS context and update
+        let mut tx = begin_with_rls(&self.pool, delivered_by)
+            .await
+            .context("Failed to begin transaction")?;
+
+        let document = sqlx::query_as!(
+            GeneratedDocument,
+            r#"
+            UPDATE generated_documents
+            SET
+                status = 'DELIVERED',
+                delivered_to = $2,
+                delivered_at = NOW(),
+                updated_by = $3,
+                updated_at = NOW()
+            WHERE id = $1 AND status = 'GENERATED'
+            RETURNING
+                id, template_id, patient_id, visit_id, visit_date, provider_id,
+                document_type, document_title, document_filename,
+                file_path, file_size_bytes, file_hash,
+                template_version, generation_data,
+                status, generation_error,
+                delivered_to, delivered_at,
+                expires_at, deleted_at,
+                is_signed, signature_hash, signed_at, signed_by,
+                created_at, updated_at, created_by, updated_by
+            "#,
+            id,
+            data.delivered_to,
+            delivered_by
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .context("Failed to mark document as delivered")?;
+
+        tx.commit().await.context("Failed to commit transaction")?;
+
+        if let Err(e) = PatientCommunicationService::record_document(
+            &self.pool,
+            document.id,
+            data.delivery_method.as_deref(),
+            delivered_by,
+        )
+        .await
+        {
+            tracing::warn!(
+                "Failed to log delivery of document {} in the communication log: {}",
+                document.id, e
+            );
+        }
+
+        Ok(GeneratedDocumentResponse::from(document))
+    }
+
+    /// Sign document digitally with visible signature block
+    pub async fn sign_document(
+        &self,
+        id: Uuid,
+        signed_by: Uuid,
+    ) -> Result<GeneratedDocumentResponse> {
+        // Start transaction for RLS context and update
+        let mut tx = begin_with_rls(&self.pool, signed_by)
+            .await
+            .context("Failed to begin transaction")?;
+
+        // Get current document
+        let doc = sqlx::query!(
+            r#"
+            SELECT
+                file_hash, document_title, patient_id, created_at,
+                is_signed, template_id, generation_data, file_path
+            FROM generated_documents
+            WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .context("Failed to fetch document for signing")?;
+
+        // Get template info
+        let template = sqlx::query!(
+            r#"
+            SELECT
+                template_html, header_html, footer_html, css_styles,
+                page_size, page_orientation,
+                margin_top_mm, margin_bottom_mm, margin_left_mm, margin_right_mm
+            FROM document_templates
+            WHERE id = $1
+            "#,
+            doc.template_id
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .context("Failed to fetch template for signing")?;
+
+        // Validate file path is within storage directory (path traversal prevention)
+        {
+            let file_path = std::path::Path::new(&doc.file_path);
+            let canonical_file = file_path
+                .canonicalize()
+                .context("Failed to resolve document file path")?;
+            let canonical_storage = self
+                .storage_path
+                .canonicalize()
+                .context("Failed to resolve storage path")?;
+            if !canonical_file.starts_with(&canonical_storage) {
+                anyhow::bail!("Document file path is outside the storage directory");
+            }
+        }
+
+        // Prevent double-signing
+        if doc.is_signed.unwrap_or(false) {
+            anyhow::bail!("Document is already signed");
+        }
+
+        // Fetch signer information
+        let signer = sqlx::query!(
+            r#"
+            SELECT first_name, last_name, role, email
+            FROM users WHERE id = $1
+            "#,
+            signed_by
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .context("Failed to fetch signer information")?;
+
+        let signer_name = format!("{} {}", signer.first_name, signer.last_name);
+        let signed_at = Utc::now();
+
+        // Create signature hash (SHA-256 of file_hash + signer + timestamp)
+        let signature_content = format!(
+            "{}:{}:{}:{}",
+            doc.file_hash.clone().unwrap_or_default(),
+            signed_by,
+            doc.patient_id,
+            signed_at.timestamp()
+        );
+        let signature_hash = self.calculate_hash(signature_content.as_bytes());
+
+        // Regenerate PDF with signature block
+        let new_pdf_bytes = if let Some(ref tpl) = template {
+            // Decrypt generation data to get original variables
+            let variables: serde_json::Value = if let Some(ref gen_data) = doc.generation_data {
+                if let Some(encrypted) = gen_data.get("encrypted").and_then(|v: &serde_json::Value| v.as_str()) {
+                    self.encryption_key.decrypt_json(encrypted)
+                        .unwrap_or_else(|_| serde_json::json!({}))
+                } else {
+                    serde_json::json!({})
+                }
+            } else {
+                serde_json::json!({})
+            };
+
+            // Render the original content with variables
+            let rendered_html = self.substitute_variables(&tpl.template_html, &variables)
+                .context("Failed to substitute template variables")?;
+
+            // Create signature block HTML
+            let signature_block = format!(
+                r#"
+                <div style="margin-top: 40px; padding-top: 20px; border-top: 2px solid #333;">
+                    <div style="text-align: center; margin-bottom: 20px;">
+                        <strong>═══════════════════════════════════════════════════════════</strong>
+                    </div>
+                    <div style="text-align: center; margin-bottom: 15px;">
+                        <strong>DIGITALLY SIGNED DOCUMENT</strong>
+                    </div>
+                    <div style="margin: 10px 0;">
+                        <strong>Signed by:</strong> Dr. {}
+                    </div>
+                    <div style="margin: 10px 0;">
+                        <strong>Role:</strong> {}
+                    </div>
+                    <div style="margin: 10px 0;">
+                        <strong>Date and Time:</strong> {}
+                    </div>
+                    <div style="margin: 10px 0;">
+                        <strong>Digital Signature Hash:</strong>
+                    </div>
+                    <div style="font-family: monospace; font-size: 10px; word-break: break-all; margin: 5px 0;">
+                        {}
+                    </div>
+                    <div style="text-align: center; margin-top: 20px;">
+                        <strong>═══════════════════════════════════════════════════════════</strong>
+                    </div>
+                    <div style="text-align: center; font-size: 10px; margin-top: 10px; color: #666;">
+                        This document has been digitally signed and any modifications will invalidate the signature.
+                    </div>
+                </div>
+                "#,
+                signer_name,
+                signer.role,
+                signed_at.format("%Y-%m-%d %H:%M:%S UTC"),
+                signature_hash
+            );
+
+            // Combine original content with signature block
+            let signed_content = format!("{}\n{}", rendered_html, signature_block);
+
+            // Render header/footer with variables
+            let rendered_header = tpl.header_html
+                .as_ref()
+                .map(|h| self.substitute_variables(h, &variables))
+                .transpose()
+                .context("Failed to substitute header variables")?;
+            let rendered_footer = tpl.footer_html
+                .as_ref()
+                .map(|f| self.substitute_variables(f, &variables))
+                .transpose()
+                .context("Failed to substitute footer variables")?;
+
+            // Parse page size and orientation
+            let page_size = tpl.page_size
+                .as_deref()
+                .map(PageSize::from_str)
+                .unwrap_or(PageSize::A4);
+            let orientation = tpl.page_orientation
+                .as_deref()
+                .map(PageOrientation::from_str)
+                .unwrap_or(PageOrientation::Portrait);
+
+            // Generate new PDF with signature
+            self.render_pdf_from_html(
+                &signed_content,
+                rendered_header.as_deref(),
+                rendered_footer.as_deref(),
+                tpl.css_styles.as_deref(),
+                page_size,
+                orientation,
+                tpl.margin_top_mm.unwrap_or(20),
+                tpl.margin_bottom_mm.unwrap_or(20),
+                tpl.margin_left_mm.unwrap_or(20),
+                tpl.margin_right_mm.unwrap_or(20),
+            ).context("Failed to render signed PDF")?
+        } else {
+            // No template body available, keep original file
+            tokio::fs::read(&doc.file_path).await
+                .context("Failed to read original PDF file")?
+        };
+
+        // Calculate new file hash
+        let new_file_hash = self.calculate_hash(&new_pdf_bytes);
+        let new_file_size = new_pdf_bytes.len() as i64;
+
+        // Update the stored file
+        tokio::fs::write(&doc.file_path, &new_pdf_bytes).await
+            .context("Failed to update signed PDF file")?;
+
+        // Update database with signature info and new file hash
+        let document = sqlx::query_as!(
+            GeneratedDocument,
+            r#"
+            UPDATE generated_documents
+            SET
+                is_signed = true,
+                signature_hash = $2,
+                signed_at = $3,
+                signed_by = $4,
+                file_hash = $5,
+                file_size_bytes = $6,
+                updated_by = $4,
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING
+                id, template_id, patient_id, visit_id, visit_date, provider_id,
+                document_type, document_title, document_filename,
+                file_path, file_size_bytes, file_hash,
+                template_version, generation_data,
+                status, generation_error,
+                delivered_to, delivered_at,
+                expires_at, deleted_at,
+                is_signed, signature_hash, signed_at, signed_by,
+                created_at, updated_at, created_by, updated_by
+            "#,
+            id,
+            signature_hash,
+            signed_at,
+            signed_by,
+            new_file_hash,
+            new_file_size
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .context("Failed to sign document")?;
+
+        tx.commit().await.context("Failed to commit transaction")?;
+
+        Ok(GeneratedDocumentResponse::from(document))
+    }
+
+    /// Delete document (soft delete)
+    pub async fn delete_document(&self, id: Uuid, deleted_by: Uuid) -> Result<()> {
+        // Start transaction for RLS context and update
+        let mut tx = begin_with_rls(&self.pool, deleted_by)
+            .await
+            .context("Failed to begin transaction")?;
+
+        // Check if document is signed - signed documents cannot be deleted
+        let is_signed = sqlx::query_scalar!(
+            r#"SELECT is_signed FROM generated_documents WHERE id = $1"#,
+            id
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .context("Failed to check document signing status")?;
+
+        if let Some(signed) = is_signed {
+            if signed.unwrap_or(false) {
+                anyhow::bail!("Signed documents cannot be deleted");
+            }
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE generated_documents
+            SET status = 'DELETED', deleted_at = NOW(), updated_by = $2, updated_at = NOW()
+            WHERE id = $1 AND status NOT IN ('FAILED', 'DELETED')
+            "#,
+            id,
+            deleted_by
+        )
+        .execute(&mut *tx)
+        .await
+        .context("Failed to delete document")?;
+
+        tx.commit().await.context("Failed to commit transaction")?;
+
+        Ok(())
+    }
+
+    /// Get document statistics
+    pub async fn get_statistics(&self) -> Result<DocumentStatistics> {
+        let total = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) as "count!" FROM generated_documents WHERE status != 'DELETED'"#
+        )
+        .fetch_one(&self.pool)
+        .await
+        .context("Failed to count documents")?;
+
+        let signed = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) as "count!" FROM generated_documents WHERE is_signed = true AND status != 'DELETED'"#
+        )
+        .fetch_one(&self.pool)
+        .await
+        .context("Failed to count signed documents")?;
+
+        let delivered = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) as "count!" FROM generated_documents WHERE status = 'DELIVERED'"#
+        )
+        .fetch_one(&self.pool)
+        .await
+        .context("Failed to count delivered documents")?;
+
+        let total_size = sqlx::query_scalar!(
+            r#"SELECT COALESCE(SUM(file_size_bytes), 0)::BIGINT as "sum!" FROM generated_documents WHERE status != 'DELETED'"#
+        )
+        .fetch_one(&self.pool)
+        .await
+        .context("Failed to sum file sizes")?;
+
+        // Get counts by type
+        let type_counts = sqlx::query!(
+            r#"
+            SELECT document_type, COUNT(*) as "count!"
+            FROM generated_documents
+            WHERE status != 'DELETED'
+            GROUP BY document_type
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to get type counts")?;
+
+        let by_type: Vec<DocumentTypeCount> = type_counts
+            .into_iter()
+            .filter_map(|r| {
+                DocumentType::from_str(&r.document_type).map(|dt| DocumentTypeCount {
+                    document_type: dt,
+                    count: r.count,
+                })
+            })
+            .collect();
+
+        // Get counts by status
+        let status_counts = sqlx::query!(
+            r#"
+            SELECT status, COUNT(*) as "count!"
+            FROM generated_documents
+            GROUP BY status
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to get status counts")?;
+
+        let by_status: Vec<DocumentStatusCount> = status_counts
+            .into_iter()
+            .filter_map(|r| {
+                DocumentStatus::from_str(&r.status).map(|s| DocumentStatusCount {
+                    status: s,
+                    count: r.count,
+                })
+            })
+            .collect();
+
+        Ok(DocumentStatistics {
+            total_documents: total,
+            by_type,
+            by_status,
+            signed_count: signed,
+            delivered_count: delivered,
+            total_size_bytes: total_size,
+        })
+    }
+
+    // ==================== Helper Methods ====================
+
+    /// Load the patient, provider and clinic data every template can use
+    async fn load_generation_context(
+        &self,
+        patient_id: Uuid,
+        provider_id: Uuid,
+    ) -> Result<GenerationContext> {
+        // Start transaction for RLS context to fetch patient data
+        let mut tx = begin_with_rls(&self.pool, provider_id)
+            .await
+            .context("Failed to begin transaction")?;
+
+        tracing::debug!("RLS context set successfully");
+
+        // Fetch patient data from database within RLS context (data is encrypted)
+        let patient_encrypted = sqlx::query!(
+            r#"
+            SELECT id, first_name, last_name, middle_name, date_of_birth,
+                   gender, fiscal_code, email, phone_primary
+            FROM patients
+            WHERE id = $1
+            "#,
+            patient_id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .context("Failed to fetch patient for document generation")?;
+
+        // Decrypt patient data
+        let patient_first_name = self.encryption_key.decrypt(&patient_encrypted.first_name)
+            .context("Failed to decrypt patient first_name")?;
+        let patient_last_name = self.encryption_key.decrypt(&patient_encrypted.last_name)
+            .context("Failed to decrypt patient last_name")?;
+        let patient_middle_name = patient_encrypted.middle_name
+            .as_ref()
+            .map(|m| self.encryption_key.decrypt(m))
+            .transpose()
+            .context("Failed to decrypt patient middle_name")?;
+        let patient_date_of_birth = self.encryption_key.decrypt(&patient_encrypted.date_of_birth)
+            .context("Failed to decrypt patient date_of_birth")?;
+        let patient_fiscal_code = patient_encrypted.fiscal_code
+            .as_ref()
+            .map(|f| self.encryption_key.decrypt(f))
+            .transpose()
+            .context("Failed to decrypt patient fiscal_code")?;
+        let patient_email = patient_encrypted.email
+            .as_ref()
+            .map(|e| self.encryption_key.decrypt(e))
+            .transpose()
+            .context("Failed to decrypt patient email")?;
+        let patient_phone = patient_encrypted.phone_primary
+            .as_ref()
+            .map(|p| self.encryption_key.decrypt(p))
+            .transpose()
+            .context("Failed to decrypt patient phone")?;
+
+        // Fetch provider (user) data for template
+        let provider = sqlx::query!(
+            r#"
+            SELECT id, first_name, last_name, email
+            FROM users
+            WHERE id = $1
+            "#,
+            provider_id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .context("Failed to fetch provider for document generation")?;
+
+        // Fetch clinic settings from system_settings table
+        let clinic_settings = sqlx::query!(
+            r#"
+            SELECT setting_key, setting_value
+            FROM system_settings
+            WHERE setting_group = 'clinic'
+            "#
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .context("Failed to fetch clinic settings")?;
+
+        // Active allergies, for templates that print them (empty for roles that cannot read them)
+        let patient_allergies =
+            PatientAllergyService::active_allergies(&mut tx, &self.encryption_key, patient_id)
+                .await
+                .context("Failed to fetch patient allergies")?;
+
+        // Commit transaction after fetching data
+        tx.commit().await.context("Failed to commit data fetch transaction")?;
+
+        tracing::debug!("Patient and provider data fetched successfully");
+
+        // Fetch practice logo (outside transaction)
+        let logo_data = match FileUploadService::get_logo(&self.pool).await {
+            Ok(Some(logo)) => {
+                // Read logo file and convert to base64 data URI
+                match FileUploadService::read_file(&logo.storage_path).await {
+                    Ok(bytes) => {
+                        let base64_data = BASE64.encode(&bytes);
+                        let data_uri = format!("data:{};base64,{}", logo.mime_type, base64_data);
+                        tracing::debug!("Logo loaded successfully, size: {} bytes", bytes.len());
+                        Some(data_uri)
+                    }
+                    Err(e) => {
+                        tracing::warn!("Failed to read logo file: {}", e);
+                        None
+                    }
+                }
+            }
+            Ok(None) => {
+                tracing::debug!("No practice logo configured");
+                None
+            }
+            Err(e) => {
+                tracing::warn!("Failed to fetch logo: {}", e);
+                None
+            }
+        };
+
+        // Fetch practice branding (colors, logo variants) - defaults on failure
+        let branding_data = match BrandingService::new(self.pool.clone()).document_branding().await {
+            Ok(branding) => branding,
+            Err(e) => {
+                tracing::warn!("Failed to fetch branding: {}", e);
+                serde_json::json!({
+                    "primary_color": crate::models::branding::DEFAULT_PRIMARY_COLOR,
+                    "accent_color": crate::models::branding::DEFAULT_DOCUMENT_ACCENT_COLOR,
+                    "logo_dark": null,
+                    "logo_compact": null,
+                })
+            }
+        };
+
+        // Build patient data for template (using decrypted values)
+        let patient_full_name = if let Some(ref middle) = patient_middle_name {
+            format!("{} {} {}", patient_first_name, middle, patient_last_name)
+        } else {
+            format!("{} {}", patient_first_name, patient_last_name)
+        };
+
+        let patient_data = serde_json::json!({
+            "id": patient_encrypted.id.to_string(),
+            "first_name": patient_first_name,
+            "last_name": patient_last_name,
+            "middle_name": patient_middle_name,
+            "full_name": patient_full_name,
+            "date_of_birth": patient_date_of_birth,
+            "gender": patient_encrypted.gender,
+            "fiscal_code": patient_fiscal_code.unwrap_or_else(|| "none".to_string()),
+            "email": patient_email,
+            "phone": patient_phone,
+            "allergies": patient_allergies,
+        });
+
+        // Build provider data for template
+        let provider_full_name = format!("{} {}", provider.first_name, provider.last_name);
+        let provider_data = serde_json::json!({
+            "id": provider.id.to_string(),
+            "first_name": provider.first_name,
+            "last_name": provider.last_name,
+            "full_name": provider_full_name,
+            "email": provider.email,
+            // Default values for fields that may not exist in users table
+            "specialization": "Medico Chirurgo",
+            "license_number": "N/D",
+        });
+
+        // Build clinic data from system_settings
+        let mut clinic_name = "Studio Medico".to_string();
+        let mut clinic_address = String::new();
+        let mut clinic_phone = String::new();
+        let mut clinic_email = String::new();
+        let mut clinic_vat = String::new();
+        let mut clinic_website = String::new();
+        let mut clinic_fax = String::new();
+
+        for setting in &clinic_settings {
+            // Extract string value from JSON (removing quotes if present)
+            let value = match &setting.setting_value {
+                serde_json::Value::String(s) => s.clone(),
+                v => v.to_string().trim_matches('"').to_string(),
+            };
+            match setting.setting_key.as_str() {
+                "clinic.name" => clinic_name = value,
+                "clinic.address" => clinic_address = value,
+                "clinic.phone" => clinic_phone = value,
+                "clinic.email" => clinic_email = value,
+                "clinic.vat_number" => clinic_vat = value,
+                "clinic.website" => clinic_website = value,
+                "clinic.fax" => clinic_fax = value,
+                _ => {}
             }
         }
 
-        sqlx::query!(
-            r#"
-            UPDATE generated_documents
-            SET status = 'DELETED', deleted_at = NOW(), updated_by = $2, updated_at = NOW()
-            WHERE id = $1 AND status NOT IN ('FAILED', 'DELETED')
-            "#,
-            id,
-            deleted_by
-        )
-        .execute(&mut *tx)
-        .await
-        .context("Failed to delete document")?;
+        // Parse address into components if possible (format: "street, city, province")
+        let address_parts: Vec<&str> = clinic_address.split(',').map(|s| s.trim()).collect();
+        let (street, city, province) = match address_parts.as_slice() {
+            [street, city, province, ..] => (street.to_string(), city.to_string(), province.to_string()),
+            [street, city] => (street.to_string(), city.to_string(), String::new()),
+            [street] => (street.to_string(), String::new(), String::new()),
+            [] => (String::new(), String::new(), String::new()),
+        };
 
-        tx.commit().await.context("Failed to commit transaction")?;
+        let clinic_data = serde_json::json!({
+            "name": clinic_name,
+            "address": street,
+            "full_address": clinic_address,
+            "city": city,
+            "province": province,
+            "phone": clinic_phone,
+            "fax": clinic_fax,
+            "email": clinic_email,
+            "website": clinic_website,
+            "vat_number": clinic_vat,
+            "logo": logo_data,
+            "branding": branding_data,
+        });
 
-        Ok(())
+        Ok(GenerationContext {
+            patient: patient_data,
+            provider: provider_data,
+            clinic: clinic_data,
+        })
     }
 
-    /// Get document statistics
-    pub async fn get_statistics(&self) -> Result<DocumentStatistics> {
-        let total = sqlx::query_scalar!(
-            r#"SELECT COUNT(*) as "count!" FROM generated_documents WHERE status != 'DELETED'"#
-        )
-        .fetch_one(&self.pool)
-        .await
-        .context("Failed to count documents")?;
+    /// Render a template with the given variables to PDF and store it
+    async fn render_and_store(
+        &self,
+        template: &DocumentTemplateResponse,
+        variables: &serde_json::Value,
+    ) -> Result<StoredPdf> {
+        // Perform variable substitution on main template
+        let rendered_html = self.substitute_variables(&template.template_html, variables)
+            .context("Failed to substitute template variables")?;
 
-        let signed = sqlx::query_scalar!(
-            r#"SELECT COUNT(*) as "count!" FROM generated_documents WHERE is_signed = true AND status != 'DELETED'"#
-        )
-        .fetch_one(&self.pool)
-        .await
-        .context("Failed to count signed documents")?;
+        // Also substitute variables in header and footer
+        let rendered_header = template.header_html
+            .as_ref()
+            .map(|h| self.substitute_variables(h, variables))
+            .transpose()
+            .context("Failed to substitute header variables")?;
+        let rendered_footer = template.footer_html
+            .as_ref()
+            .map(|f| self.substitute_variables(f, variables))
+            .transpose()
+            .context("Failed to substitute footer variables")?;
 
-        let delivered = sqlx::query_scalar!(
-            r#"SELECT COUNT(*) as "count!" FROM generated_documents WHERE status = 'DELIVERED'"#
-        )
-        .fetch_one(&self.pool)
-        .await
-        .context("Failed to count delivered documents")?;
+        tracing::debug!("Template variables substituted successfully");
 
-        let total_size = sqlx::query_scalar!(
-            r#"SELECT COALESCE(SUM(file_size_bytes), 0)::BIGINT as "sum!" FROM generated_documents WHERE status != 'DELETED'"#
-        )
-        .fetch_one(&self.pool)
-        .await
-        .context("Failed to sum file sizes")?;
+        // Generate PDF
+        let pdf_bytes = self.render_pdf_from_html(
+            &rendered_html,
+            rendered_header.as_deref(),
+            rendered_footer.as_deref(),
+            template.css_styles.as_deref(),
+            template.page_size,
+            template.page_orientation,
+            template.margin_top_mm,
+            template.margin_bottom_mm,
+            template.margin_left_mm,
+            template.margin_right_mm,
+        ).context("Failed to render PDF from HTML")?;
 
-        // Get counts by type
-        let type_counts = sqlx::query!(
-            r#"
-            SELECT document_type, COUNT(*) as "count!"
-            FROM generated_documents
-            WHERE status != 'DELETED'
-            GROUP BY document_type
-            "#
-        )
-        .fetch_all(&self.pool)
-        .await
-        .context("Failed to get type counts")?;
+        tracing::debug!("PDF generated successfully, size: {} bytes", pdf_bytes.len());
 
-        let by_type: Vec<DocumentTypeCount> = type_counts
-            .into_iter()
-            .filter_map(|r| {
-                DocumentType::from_str(&r.document_type).map(|dt| DocumentTypeCount {
-                    document_type: dt,
-                    count: r.count,
-                })
-            })
-            .collect();
+        // Calculate file hash
+        let file_hash = self.calculate_hash(&pdf_bytes);
 
-        // Get counts by status
-        let status_counts = sqlx::query!(
-            r#"
-            SELECT status, COUNT(*) as "count!"
-            FROM generated_documents
-            GROUP BY status
-            "#
-        )
-        .fetch_all(&self.pool)
-        .await
-        .context("Failed to get status counts")?;
+        // Generate filename
+        let filename = format!(
+            "{}_{}.pdf",
+            template.document_type.as_str().to_lowercase(),
+            Utc::now().format("%Y%m%d_%H%M%S")
+        );
 
-        let by_status: Vec<DocumentStatusCount> = status_counts
-            .into_iter()
-            .filter_map(|r| {
-                DocumentStatus::from_str(&r.status).map(|s| DocumentStatusCount {
-                    status: s,
-                    count: r.count,
-                })
-            })
-            .collect();
+        // Store file
+        let file_path = self.store_file(&filename, &pdf_bytes).await
+            .context("Failed to store PDF file")?;
 
-        Ok(DocumentStatistics {
-            total_documents: total,
-            by_type,
-            by_status,
-            signed_count: signed,
-            delivered_count: delivered,
-            total_size_bytes: total_size,
+        tracing::debug!("PDF stored at: {}", file_path);
+
+        Ok(StoredPdf {
+            filename,
+            file_path,
+            file_size_bytes: pdf_bytes.len() as i64,
+            file_hash,
         })
     }
 
-    // ==================== Helper Methods ====================
-
     /// Substitute variables in template HTML using minijinja
     fn substitute_variables(
         &self,
@@ -1442,6 +2924,8 @@ impl DocumentService {
             env.set_undefined_behavior(UndefinedBehavior::Lenient);
             // Enable HTML autoescaping to prevent XSS via user-supplied data
             env.set_auto_escape_callback(|_name| AutoEscape::Html);
+            // Curated formatting filters (dates, currency, age, fiscal code)
+            crate::services::template_filters::register(&mut env);
             env.add_template("document", template)
                 .context("Failed to parse template")?;
 
@@ -1478,7 +2962,10 @@ impl DocumentService {
     ) -> Result<Vec<u8>> {
         #[cfg(feature = "pdf-export")]
         {
-            use genpdf::{elements::Paragraph, fonts, Document, SimplePageDecorator};
+            use genpdf::{
+                elements::{PageBreak, Paragraph},
+                fonts, Document, SimplePageDecorator,
+            };
 
             // Get page dimensions
             let (width, height) = page_size.dimensions_mm();
@@ -1518,11 +3005,16 @@ impl DocumentService {
                 doc.push(Paragraph::new(html_to_text(header_text)));
             }
 
-            // Add main content (HTML to plain text)
-            let plain_text = html_to_text(content);
-            for line in plain_text.lines() {
-                if !line.trim().is_empty() {
-                    doc.push(Paragraph::new(line));
+            // Add main content (HTML to plain text), honouring page-break markers
+            for (page, html) in split_pages(content).into_iter().enumerate() {
+                if page > 0 {
+                    doc.push(PageBreak::new());
+                }
+                let plain_text = html_to_text(html);
+                for line in plain_text.lines() {
+                    if !line.trim().is_empty() {
+                        doc.push(Paragraph::new(line));
+                    }
                 }
             }
 
@@ -1580,6 +3072,405 @@ impl DocumentService {
     }
 }
 
+/// Visits loaded per query for a full chart summary
+const SUMMARY_VISIT_PAGE_SIZE: i64 = 100;
+
+/// Demographics printed by the print bundle and the clinical summary
+fn demographics_context(patient: PatientDto) -> serde_json::Value {
+    serde_json::json!({
+        "medical_record_number": patient.medical_record_number,
+        "address": patient.address,
+        "emergency_contact": patient.emergency_contact,
+        "blood_type": patient.blood_type,
+        "allergies": patient.allergies.unwrap_or_default(),
+        "chronic_conditions": patient.chronic_conditions.unwrap_or_default(),
+    })
+}
+
+/// One visit of a clinical summary
+///
+/// Every key the template prints is present (null when not recorded).
+fn summary_visit_context(
+    visit: &VisitResponse,
+    diagnoses: &[VisitDiagnosisResponse],
+    prescriptions: &[PrescriptionResponse],
+) -> serde_json::Value {
+    let provider = match (&visit.provider_first_name, &visit.provider_last_name) {
+        (Some(first), Some(last)) => format!("Dr. {} {}", first, last),
+        _ => String::new(),
+    };
+
+    serde_json::json!({
+        "date": visit.visit_date.format("%d/%m/%Y").to_string(),
+        "type": visit.visit_type,
+        "status": visit.status,
+        "provider": provider,
+        "chief_complaint": visit.chief_complaint,
+        "vitals": visit.vitals,
+        "subjective": visit.subjective,
+        "objective": visit.objective,
+        "assessment": visit.assessment,
+        "plan": visit.plan,
+        "diagnoses": diagnoses
+            .iter()
+            .map(|d| {
+                serde_json::json!({
+                    "code": d.icd10_code,
+                    "description": d.icd10_description,
+                    "type": d.diagnosis_type,
+                    "primary": d.is_primary,
+                })
+            })
+            .collect::<Vec<_>>(),
+        "prescriptions": prescriptions
+            .iter()
+            .map(summary_prescription_context)
+            .collect::<Vec<_>>(),
+        "follow_up_date": visit
+            .follow_up_date
+            .map(|d| d.format("%d/%m/%Y").to_string()),
+        "follow_up_notes": visit.follow_up_notes,
+    })
+}
+
+fn summary_prescription_context(rx: &PrescriptionResponse) -> serde_json::Value {
+    serde_json::json!({
+        "name": rx.medication_name,
+        "generic_name": rx.generic_name,
+        "dosage": rx.dosage,
+        "frequency": rx.frequency,
+        "duration": rx.duration,
+        "instructions": rx.instructions,
+        "status": rx.status,
+    })
+}
+
+/// Active problems for the print bundle: one entry per ICD-10 code, dated
+/// from the first visit it was recorded in
+fn active_problems(diagnoses: &[VisitDiagnosisResponse]) -> Vec<serde_json::Value> {
+    let mut sorted: Vec<&VisitDiagnosisResponse> = diagnoses.iter().filter(|d| d.is_active).collect();
+    sorted.sort_by_key(|d| d.visit_date);
+
+    let mut seen = std::collections::HashSet::new();
+    sorted
+        .into_iter()
+        .filter(|d| seen.insert(d.icd10_code.clone()))
+        .map(|d| {
+            serde_json::json!({
+                "code": d.icd10_code,
+                "description": d.icd10_description,
+                "type": d.diagnosis_type,
+                "primary": d.is_primary,
+                "since": d.visit_date.format("%d/%m/%Y").to_string(),
+            })
+        })
+        .collect()
+}
+
+/// `lab` template variables for a requisition
+///
+/// Keeps the keys of the empty `lab` default (`tests`, `clinical_info`,
+/// `urgency`, `fasting`) alongside the ones the lab request templates print.
+fn lab_requisition_context(
+    order: &LabOrderResponse,
+    request: &GenerateLabRequisitionRequest,
+) -> Result<serde_json::Value> {
+    let requisition_number = order
+        .requisition_number
+        .as_deref()
+        .ok_or_else(|| anyhow::anyhow!("Lab order {} has no requisition number", order.id))?;
+    let barcode = barcode::code39_data_uri(requisition_number).ok_or_else(|| {
+        anyhow::anyhow!("Requisition number {} cannot be barcoded", requisition_number)
+    })?;
+
+    let urgent = order.urgency == LabUrgency::Urgent.as_str();
+    let priority = match (request.language, urgent) {
+        (TemplateLanguage::Italian, true) => "Urgente",
+        (TemplateLanguage::Italian, false) => "Ordinario",
+        (TemplateLanguage::English, true) => "Urgent",
+        (TemplateLanguage::English, false) => "Routine",
+    };
+
+    let tests: Vec<serde_json::Value> = order
+        .tests
+        .iter()
+        .map(|test| {
+            serde_json::json!({
+                "code": test.loinc_code,
+                "loinc_code": test.loinc_code,
+                "name": test.test_name,
+                "priority": priority,
+            })
+        })
+        .collect();
+
+    let clinical_info = order.clinical_info.clone().unwrap_or_default();
+    let diagnostic_question = request
+        .diagnostic_question
+        .clone()
+        .unwrap_or_else(|| clinical_info.clone());
+
+    Ok(serde_json::json!({
+        "order_id": order.id,
+        "requisition_number": requisition_number,
+        "barcode": barcode,
+        "tests": tests,
+        "clinical_info": clinical_info,
+        "clinical_notes": clinical_info,
+        "diagnostic_question": diagnostic_question,
+        "urgency": priority,
+        "fasting": order.fasting,
+        "fasting_required": order.fasting,
+        "lab_name": order.lab_name,
+        "special_instructions": request.special_instructions,
+        "ordered_at": order.ordered_at.format("%d/%m/%Y").to_string(),
+    }))
+}
+
+/// Template the imaging referral letter is rendered with by default
+const IMAGING_REFERRAL_TEMPLATE_KEY: &str = "imaging_referral_it";
+
+/// `imaging` and `referral` template variables for an imaging referral letter
+fn imaging_referral_context(
+    order: &ImagingOrderResponse,
+    request: &GenerateImagingReferralRequest,
+) -> (serde_json::Value, serde_json::Value) {
+    let italian = request.language == TemplateLanguage::Italian;
+    let modality = ImagingModality::from_db(&order.modality)
+        .map(|m| m.label(request.language).to_string())
+        .unwrap_or_else(|| order.modality.clone());
+    let laterality = order.laterality.as_deref().map(|l| match (l, italian) {
+        ("LEFT", true) => "sinistra",
+        ("RIGHT", true) => "destra",
+        ("BILATERAL", true) => "bilaterale",
+        ("LEFT", false) => "left",
+        ("RIGHT", false) => "right",
+        _ => "bilateral",
+    });
+    let urgency = match (order.urgency == ImagingUrgency::Urgent.as_str(), italian) {
+        (true, true) => "Urgente",
+        (false, true) => "Ordinaria",
+        (true, false) => "Urgent",
+        (false, false) => "Routine",
+    };
+
+    let mut exam = format!("{} {}", modality, order.body_part);
+    if let Some(laterality) = laterality {
+        exam = format!("{} {}", exam, laterality);
+    }
+    if order.contrast {
+        exam.push_str(if italian { " con mezzo di contrasto" } else { " with contrast" });
+    }
+
+    let imaging = serde_json::json!({
+        "order_id": order.id,
+        "modality": modality,
+        "modality_code": order.modality,
+        "body_part": order.body_part,
+        "laterality": laterality,
+        "contrast": order.contrast,
+        "exam": exam,
+        "clinical_question": order.clinical_question,
+        "urgency": urgency,
+        "facility": order.facility,
+        "ordered_at": order.ordered_at.format("%d/%m/%Y").to_string(),
+    });
+
+    // Keys of the empty `referral` default plus every one the referral
+    // letter templates print, which fail to render on a missing key
+    let (specialty, department) = if italian {
+        ("Radiologia", "Diagnostica per immagini")
+    } else {
+        ("Radiology", "Diagnostic imaging")
+    };
+    let referral = serde_json::json!({
+        "specialty": specialty,
+        "specialist_name": order.facility.as_deref().unwrap_or(specialty),
+        "specialist_department": department,
+        "specialist_address": "",
+        "urgency": urgency,
+        "reason": order.clinical_question,
+        "clinical_info": order.clinical_question,
+        "clinical_history": request.clinical_history.clone().unwrap_or_default(),
+        "current_medications": "",
+        "allergies": "",
+        "examination_findings": "",
+        "suspected_diagnosis": "",
+        "request": exam,
+    });
+
+    (imaging, referral)
+}
+
+/// `referral` variables for a referral letter
+///
+/// Every key the referral letter templates print is present, since they fail
+/// to render on a missing one.
+fn referral_letter_context(
+    referral: &ReferralResponse,
+    request: &GenerateReferralLetterRequest,
+) -> serde_json::Value {
+    let urgent = referral.urgency == ReferralUrgency::Urgent.as_str();
+    let urgency = match (urgent, request.language) {
+        (true, TemplateLanguage::Italian) => "Urgente",
+        (false, TemplateLanguage::Italian) => "Ordinaria",
+        (true, TemplateLanguage::English) => "Urgent",
+        (false, TemplateLanguage::English) => "Routine",
+    };
+
+    serde_json::json!({
+        "referral_id": referral.id,
+        "specialty": referral.specialty,
+        "specialist_name": referral
+            .specialist_name
+            .as_deref()
+            .unwrap_or(&referral.specialty),
+        "specialist_department": referral.specialty,
+        "specialist_address": referral.specialist_contact.clone().unwrap_or_default(),
+        "urgency": urgency,
+        "reason": referral.reason,
+        "clinical_info": referral.clinical_info.clone().unwrap_or_default(),
+        "clinical_history": request
+            .clinical_history
+            .clone()
+            .or_else(|| referral.clinical_info.clone())
+            .unwrap_or_default(),
+        "current_medications": "",
+        "allergies": "",
+        "examination_findings": request.examination_findings.clone().unwrap_or_default(),
+        "suspected_diagnosis": request.suspected_diagnosis.clone().unwrap_or_default(),
+        "request": referral.reason,
+        "response_due_date": referral.response_due_date.format("%d/%m/%Y").to_string(),
+    })
+}
+
+/// Template the NRE promemoria is rendered with by default
+const NRE_PROMEMORIA_TEMPLATE_KEY: &str = "nre_promemoria_it";
+
+/// `nre` and `prescription` template variables for an NRE promemoria
+fn nre_promemoria_context(promemoria: &NrePromemoria) -> (serde_json::Value, serde_json::Value) {
+    let rx = &promemoria.prescription;
+    let medication = match &rx.generic_name {
+        Some(generic) if !generic.eq_ignore_ascii_case(&rx.medication_name) => {
+            format!("{} ({})", rx.medication_name, generic)
+        }
+        _ => rx.medication_name.clone(),
+    };
+    let instructions = match &rx.instructions {
+        Some(instructions) => format!("{} - {}", rx.frequency, instructions),
+        None => rx.frequency.clone(),
+    };
+
+    let nre = serde_json::json!({
+        "number": promemoria.nre,
+        "barcode": barcode::code39_data_uri(&promemoria.nre),
+        "fiscal_code": promemoria.patient_fiscal_code,
+        "fiscal_code_barcode": barcode::code39_data_uri(&promemoria.patient_fiscal_code),
+        "prescriber_fiscal_code": promemoria.prescriber_fiscal_code.clone().unwrap_or_default(),
+        "exemption_code": promemoria.exemption_code,
+        "aic_code": promemoria.aic_code.clone().unwrap_or_default(),
+        "medication": medication,
+        "dosage": rx.dosage,
+        "quantity": promemoria.quantity,
+        "instructions": instructions,
+        "issued_at": promemoria.issued_at.format("%d/%m/%Y").to_string(),
+    });
+
+    let mut prescription = prescription_context(rx);
+    prescription["medications"][0]["quantity"] = serde_json::json!(promemoria.quantity);
+    prescription["notes"] = serde_json::json!(format!("NRE {}", promemoria.nre));
+
+    (nre, prescription)
+}
+
+/// `prescription` template variables for a single prescription
+///
+/// Keys the prescription templates print for each medication.
+fn prescription_context(rx: &PrescriptionResponse) -> serde_json::Value {
+    serde_json::json!({
+        "medications": [{
+            "name": rx.medication_name,
+            "strength": rx.dosage,
+            "form": rx.form.clone().unwrap_or_default(),
+            "dosage": rx.frequency,
+            "instructions": rx.instructions.clone().unwrap_or_default(),
+            "quantity": rx.quantity.unwrap_or(1),
+        }],
+        "notes": rx.pharmacy_notes.clone().unwrap_or_default(),
+    })
+}
+
+/// Boxes of the fiscal code field on the ricetta
+const SSN_FISCAL_CODE_BOXES: usize = 16;
+
+/// `ricetta` template variables for an SSN prescription
+fn ssn_prescription_context(
+    rx: &PrescriptionResponse,
+    fiscal_code: Option<&str>,
+    request: &GenerateSsnPrescriptionRequest,
+) -> Result<serde_json::Value> {
+    let packages = rx.quantity.unwrap_or(1);
+    if !(1..=SSN_MAX_PACKAGES).contains(&packages) {
+        anyhow::bail!(
+            "An SSN prescription allows 1 to {} packages, the prescription has {}",
+            SSN_MAX_PACKAGES,
+            packages
+        );
+    }
+
+    // Left blank to be filled in by hand when the patient has no fiscal code
+    let mut fiscal_code_boxes: Vec<String> = fiscal_code
+        .unwrap_or_default()
+        .trim()
+        .to_uppercase()
+        .chars()
+        .take(SSN_FISCAL_CODE_BOXES)
+        .map(String::from)
+        .collect();
+    fiscal_code_boxes.resize(SSN_FISCAL_CODE_BOXES, String::new());
+
+    let priority_boxes: Vec<serde_json::Value> = SsnPriority::ALL
+        .iter()
+        .map(|p| serde_json::json!({ "code": p.code(), "selected": request.priority == Some(*p) }))
+        .collect();
+
+    let description = match &rx.generic_name {
+        Some(generic) if !generic.eq_ignore_ascii_case(&rx.medication_name) => {
+            format!("{} ({}) {}", rx.medication_name, generic, rx.dosage)
+        }
+        _ => format!("{} {}", rx.medication_name, rx.dosage),
+    };
+    let posology = match &rx.instructions {
+        Some(instructions) => format!("{} - {}", rx.frequency, instructions),
+        None => rx.frequency.clone(),
+    };
+
+    Ok(serde_json::json!({
+        "nre": rx.e_prescription_id,
+        "fiscal_code_boxes": fiscal_code_boxes,
+        "exemption_code": request.exemption_code.as_ref().map(|c| c.trim().to_uppercase()),
+        "priority_boxes": priority_boxes,
+        "items": [{
+            "description": description,
+            "form": rx.form,
+            "posology": posology,
+            "packages": packages,
+        }],
+        "total_packages": packages,
+        "notes": request.notes,
+        "date": rx.prescribed_date.format("%d/%m/%Y").to_string(),
+    }))
+}
+
+/// Marker templates use to start a new PDF page
+const PAGE_BREAK_MARKER: &str = "<div class=\"page-break\"></div>";
+
+/// Split rendered HTML into pages at each page-break marker
+#[cfg(feature = "pdf-export")]
+fn split_pages(html: &str) -> Vec<&str> {
+    html.split(PAGE_BREAK_MARKER).collect()
+}
+
 /// Simple HTML to text converter (strips tags)
 #[cfg(feature = "pdf-export")]
 fn html_to_text(html: &str) -> String {
@@ -1603,3 +3494,304 @@ fn html_to_text(html: &str) -> String {
         .collect::<Vec<_>>()
         .join("\n")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn diagnosis(code: &str, visit_date: NaiveDate, is_active: bool) -> VisitDiagnosisResponse {
+        VisitDiagnosisResponse {
+            id: Uuid::new_v4(),
+            visit_id: Uuid::new_v4(),
+            visit_date,
+            patient_id: Uuid::new_v4(),
+            icd10_code: code.to_string(),
+            icd10_description: format!("Description {}", code),
+            is_primary: false,
+            diagnosis_type: None,
+            clinical_notes: None,
+            is_active,
+            resolved_date: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            created_by: None,
+            updated_by: None,
+        }
+    }
+
+    #[test]
+    fn test_active_problems_dedupes_by_code_keeping_earliest() {
+        let diagnoses = vec![
+            diagnosis("I10", NaiveDate::from_ymd_opt(2025, 6, 1).unwrap(), true),
+            diagnosis("E11.9", NaiveDate::from_ymd_opt(2025, 3, 1).unwrap(), true),
+            diagnosis("I10", NaiveDate::from_ymd_opt(2024, 1, 15).unwrap(), true),
+            diagnosis("J45", NaiveDate::from_ymd_opt(2024, 2, 1).unwrap(), false),
+        ];
+
+        let problems = active_problems(&diagnoses);
+
+        assert_eq!(problems.len(), 2);
+        assert_eq!(problems[0]["code"], "I10");
+        assert_eq!(problems[0]["since"], "15/01/2024");
+        assert_eq!(problems[1]["code"], "E11.9");
+    }
+
+    fn lab_order(requisition_number: Option<&str>) -> LabOrderResponse {
+        LabOrderResponse {
+            id: Uuid::new_v4(),
+            patient_id: Uuid::new_v4(),
+            visit_id: None,
+            visit_date: None,
+            status: "ORDERED".to_string(),
+            urgency: "URGENT".to_string(),
+            fasting: true,
+            lab_name: None,
+            clinical_info: Some("Follow-up of type 2 diabetes".to_string()),
+            tests: vec![crate::models::LabOrderTest {
+                order_id: Uuid::new_v4(),
+                loinc_code: "4548-4".to_string(),
+                test_name: "Emoglobina glicata".to_string(),
+            }],
+            requisition_number: requisition_number.map(str::to_string),
+            requisition_document_id: None,
+            ordered_at: Utc::now(),
+            created_by: Uuid::new_v4(),
+            created_at: Utc::now(),
+            updated_by: None,
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_lab_requisition_context() {
+        let order = lab_order(Some("LAB-2026-000042"));
+        let lab = lab_requisition_context(&order, &GenerateLabRequisitionRequest::default()).unwrap();
+
+        assert_eq!(lab["requisition_number"], "LAB-2026-000042");
+        assert!(lab["barcode"].as_str().unwrap().starts_with("data:image/svg+xml;base64,"));
+        assert_eq!(lab["tests"][0]["code"], "4548-4");
+        assert_eq!(lab["tests"][0]["priority"], "Urgente");
+        assert_eq!(lab["diagnostic_question"], "Follow-up of type 2 diabetes");
+        assert_eq!(lab["fasting_required"], true);
+
+        assert!(lab_requisition_context(&lab_order(None), &GenerateLabRequisitionRequest::default()).is_err());
+    }
+
+    #[test]
+    fn test_imaging_referral_context() {
+        let order = ImagingOrderResponse {
+            id: Uuid::new_v4(),
+            patient_id: Uuid::new_v4(),
+            visit_id: None,
+            visit_date: None,
+            modality: "MRI".to_string(),
+            body_part: "ginocchio".to_string(),
+            laterality: Some("LEFT".to_string()),
+            contrast: true,
+            clinical_question: "Sospetta lesione meniscale".to_string(),
+            urgency: "URGENT".to_string(),
+            status: "ORDERED".to_string(),
+            facility: None,
+            performed_at: None,
+            reported_at: None,
+            report: None,
+            referral_document_id: None,
+            ordered_at: Utc::now(),
+            created_by: Uuid::new_v4(),
+            created_at: Utc::now(),
+            updated_by: None,
+            updated_at: Utc::now(),
+        };
+
+        let (imaging, referral) =
+            imaging_referral_context(&order, &GenerateImagingReferralRequest::default());
+        assert_eq!(imaging["modality"], "Risonanza magnetica");
+        assert_eq!(
+            imaging["exam"],
+            "Risonanza magnetica ginocchio sinistra con mezzo di contrasto"
+        );
+        assert_eq!(imaging["urgency"], "Urgente");
+        assert_eq!(referral["request"], imaging["exam"]);
+        assert_eq!(referral["reason"], "Sospetta lesione meniscale");
+        assert_eq!(referral["specialist_name"], "Radiologia");
+        assert_eq!(referral["examination_findings"], "");
+
+        let english = GenerateImagingReferralRequest {
+            language: TemplateLanguage::English,
+            ..Default::default()
+        };
+        let (imaging, _) = imaging_referral_context(&order, &english);
+        assert_eq!(imaging["exam"], "MRI ginocchio left with contrast");
+    }
+
+    #[test]
+    fn test_referral_letter_context() {
+        let referral = ReferralResponse {
+            id: Uuid::new_v4(),
+            patient_id: Uuid::new_v4(),
+            visit_id: None,
+            visit_date: None,
+            specialty: "Cardiologia".to_string(),
+            specialist_name: None,
+            specialist_contact: Some("Ospedale San Carlo, Milano".to_string()),
+            reason: "Soffio sistolico di nuova insorgenza".to_string(),
+            clinical_info: Some("Iperteso in terapia".to_string()),
+            urgency: "URGENT".to_string(),
+            status: "SENT".to_string(),
+            sent_at: Some(Utc::now()),
+            response_due_date: NaiveDate::from_ymd_opt(2026, 4, 10).unwrap(),
+            overdue: false,
+            response_received_at: None,
+            response_summary: None,
+            response_attachment_id: None,
+            referral_document_id: None,
+            last_reminder_at: None,
+            reminder_count: 0,
+            created_by: Uuid::new_v4(),
+            created_at: Utc::now(),
+            updated_by: None,
+            updated_at: Utc::now(),
+        };
+
+        let context =
+            referral_letter_context(&referral, &GenerateReferralLetterRequest::default());
+        assert_eq!(context["specialist_name"], "Cardiologia");
+        assert_eq!(context["specialist_address"], "Ospedale San Carlo, Milano");
+        assert_eq!(context["urgency"], "Urgente");
+        assert_eq!(context["clinical_history"], "Iperteso in terapia");
+        assert_eq!(context["examination_findings"], "");
+        assert_eq!(context["request"], "Soffio sistolico di nuova insorgenza");
+        assert_eq!(context["response_due_date"], "10/04/2026");
+
+        let english = GenerateReferralLetterRequest {
+            language: TemplateLanguage::English,
+            clinical_history: Some("No prior cardiac history".to_string()),
+            ..Default::default()
+        };
+        let context = referral_letter_context(&referral, &english);
+        assert_eq!(context["urgency"], "Urgent");
+        assert_eq!(context["clinical_history"], "No prior cardiac history");
+    }
+
+    fn sample_prescription() -> PrescriptionResponse {
+        PrescriptionResponse {
+            id: Uuid::new_v4(),
+            visit_id: None,
+            visit_date: None,
+            patient_id: Uuid::new_v4(),
+            provider_id: Uuid::new_v4(),
+            medication_name: "Eutirox".to_string(),
+            generic_name: Some("Levotiroxina".to_string()),
+            dosage: "50 mcg".to_string(),
+            form: Some("compresse".to_string()),
+            route: None,
+            frequency: "1 compressa al mattino".to_string(),
+            duration: None,
+            quantity: Some(2),
+            refills: 0,
+            instructions: Some("a digiuno".to_string()),
+            pharmacy_notes: None,
+            prescribed_date: NaiveDate::from_ymd_opt(2026, 4, 7).unwrap(),
+            start_date: None,
+            end_date: None,
+            status: crate::models::PrescriptionStatus::Active,
+            discontinuation_reason: None,
+            discontinued_at: None,
+            discontinued_by: None,
+            refills_remaining: None,
+            last_refill_date: None,
+            has_interactions: false,
+            interaction_warnings: None,
+            allergy_alerts: vec![],
+            e_prescription_id: Some("1200A4000000042".to_string()),
+            e_prescription_sent_at: None,
+            e_prescription_status: Some("ISSUED".to_string()),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            created_by: None,
+            updated_by: None,
+        }
+    }
+
+    #[test]
+    fn test_nre_promemoria_context() {
+        let promemoria = NrePromemoria {
+            prescription: sample_prescription(),
+            nre: "1200A4000000042".to_string(),
+            issued_at: Utc::now(),
+            patient_fiscal_code: "RSSMRA80A01H501U".to_string(),
+            aic_code: Some("004763015".to_string()),
+            exemption_code: None,
+            quantity: 2,
+            prescriber_fiscal_code: Some("BNCGVN70A01F205X".to_string()),
+        };
+
+        let (nre, prescription) = nre_promemoria_context(&promemoria);
+        assert_eq!(nre["number"], "1200A4000000042");
+        assert!(nre["barcode"].as_str().unwrap().starts_with("data:image/svg+xml;base64,"));
+        assert!(nre["fiscal_code_barcode"].is_string());
+        assert_eq!(nre["medication"], "Eutirox (Levotiroxina)");
+        assert_eq!(nre["instructions"], "1 compressa al mattino - a digiuno");
+        assert_eq!(nre["exemption_code"], serde_json::Value::Null);
+        assert_eq!(nre["quantity"], 2);
+        assert_eq!(prescription["medications"][0]["strength"], "50 mcg");
+        assert_eq!(prescription["notes"], "NRE 1200A4000000042");
+    }
+
+    #[test]
+    fn test_prescription_context() {
+        let rx = PrescriptionResponse {
+            pharmacy_notes: Some("Non sostituibile".to_string()),
+            quantity: None,
+            ..sample_prescription()
+        };
+
+        let prescription = prescription_context(&rx);
+        assert_eq!(prescription["medications"][0]["name"], "Eutirox");
+        assert_eq!(prescription["medications"][0]["quantity"], 1);
+        assert_eq!(prescription["notes"], "Non sostituibile");
+    }
+
+    #[test]
+    fn test_ssn_prescription_context() {
+        let rx = sample_prescription();
+        let request = GenerateSsnPrescriptionRequest {
+            exemption_code: Some("e01".to_string()),
+            priority: Some(SsnPriority::Short),
+            ..Default::default()
+        };
+
+        let ricetta = ssn_prescription_context(&rx, Some("rssmra80a01h501u"), &request).unwrap();
+        assert_eq!(ricetta["nre"], "1200A4000000042");
+        assert_eq!(ricetta["fiscal_code_boxes"].as_array().unwrap().len(), 16);
+        assert_eq!(ricetta["fiscal_code_boxes"][0], "R");
+        assert_eq!(ricetta["exemption_code"], "E01");
+        assert_eq!(ricetta["priority_boxes"][1]["code"], "B");
+        assert_eq!(ricetta["priority_boxes"][1]["selected"], true);
+        assert_eq!(ricetta["priority_boxes"][0]["selected"], false);
+        assert_eq!(ricetta["items"][0]["description"], "Eutirox (Levotiroxina) 50 mcg");
+        assert_eq!(ricetta["items"][0]["posology"], "1 compressa al mattino - a digiuno");
+        assert_eq!(ricetta["total_packages"], 2);
+        assert_eq!(ricetta["date"], "07/04/2026");
+
+        // Blank boxes without a fiscal code, not exempt by default
+        let ricetta = ssn_prescription_context(&rx, None, &Default::default()).unwrap();
+        assert_eq!(ricetta["fiscal_code_boxes"][15], "");
+        assert_eq!(ricetta["exemption_code"], serde_json::Value::Null);
+
+        let too_many = PrescriptionResponse {
+            quantity: Some(3),
+            ..sample_prescription()
+        };
+        assert!(ssn_prescription_context(&too_many, None, &Default::default()).is_err());
+    }
+
+    #[cfg(feature = "pdf-export")]
+    #[test]
+    fn test_split_pages() {
+        let html = "<h2>A</h2><div class=\"page-break\"></div><h2>B</h2>";
+        assert_eq!(split_pages(html), vec!["<h2>A</h2>", "<h2>B</h2>"]);
+        assert_eq!(split_pages("<p>single</p>"), vec!["<p>single</p>"]);
+    }
+}
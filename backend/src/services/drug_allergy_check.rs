@@ -0,0 +1,434 @@
+/*!
+ * Drug–Allergy Check
+ *
+ * Matches a drug being prescribed against the patient's active allergies:
+ * - Substance: the allergen names the drug's active ingredient (or the drug
+ *   itself), e.g. "Amoxicillina" against "Amoxicillina/Acido clavulanico"
+ * - Class: allergen and drug belong to the same class, recognized by ATC
+ *   code or by Italian/English names, e.g. "penicillin" against amoxicillin
+ * - Cross-reactivity: the drug belongs to a class known to cross-react with
+ *   the allergen's class (penicillins and cephalosporins or carbapenems)
+ *
+ * Allergy substances are free text, so matching is on normalized words and
+ * errs on the side of alerting: the prescriber confirms with an override
+ * reason.
+ */
+
+use crate::models::{AllergyMatchType, DrugAllergyAlert, PatientAllergyResponse};
+
+/// Drug class recognized in allergy substances and prescribed drugs
+struct DrugClass {
+    name: &'static str,
+    /// ATC codes starting with any of these belong to the class
+    atc_prefixes: &'static [&'static str],
+    /// Class names and members; single words match word prefixes
+    /// ("penicillin" matches "penicillina"), phrases match anywhere
+    terms: &'static [&'static str],
+    /// Classes that cross-react with this one
+    cross_reactive: &'static [&'static str],
+}
+
+const DRUG_CLASSES: &[DrugClass] = &[
+    DrugClass {
+        name: "Penicillins",
+        atc_prefixes: &["J01C"],
+        terms: &[
+            "penicillin",
+            "amoxicillin",
+            "ampicillin",
+            "piperacillin",
+            "oxacillin",
+            "flucloxacillin",
+            "benzylpenicillin",
+            "bacampicillin",
+            "augmentin",
+        ],
+        cross_reactive: &["Cephalosporins", "Carbapenems"],
+    },
+    DrugClass {
+        name: "Cephalosporins",
+        atc_prefixes: &["J01DB", "J01DC", "J01DD", "J01DE", "J01DI"],
+        terms: &["cephalosporin", "cefalosporin", "cef", "ceph"],
+        cross_reactive: &["Penicillins"],
+    },
+    DrugClass {
+        name: "Carbapenems",
+        atc_prefixes: &["J01DH"],
+        terms: &["carbapenem", "meropenem", "imipenem", "ertapenem"],
+        cross_reactive: &["Penicillins"],
+    },
+    DrugClass {
+        name: "Sulfonamide antibiotics",
+        atc_prefixes: &["J01EC", "J01EE"],
+        terms: &[
+            "sulfonamide",
+            "sulfamid",
+            "sulfamethoxazole",
+            "sulfametoxazolo",
+            "cotrimoxazole",
+            "cotrimossazolo",
+            "bactrim",
+        ],
+        cross_reactive: &[],
+    },
+    DrugClass {
+        name: "Macrolides",
+        atc_prefixes: &["J01FA"],
+        terms: &[
+            "macrolid",
+            "azithromycin",
+            "azitromicina",
+            "clarithromycin",
+            "claritromicina",
+            "erythromycin",
+            "eritromicina",
+        ],
+        cross_reactive: &[],
+    },
+    DrugClass {
+        name: "Fluoroquinolones",
+        atc_prefixes: &["J01MA"],
+        terms: &[
+            "quinolon",
+            "chinolon",
+            "fluorochinolon",
+            "ciprofloxacin",
+            "levofloxacin",
+            "moxifloxacin",
+            "norfloxacin",
+            "prulifloxacin",
+        ],
+        cross_reactive: &[],
+    },
+    DrugClass {
+        name: "Tetracyclines",
+        atc_prefixes: &["J01AA"],
+        terms: &[
+            "tetracyclin",
+            "tetraciclin",
+            "doxycyclin",
+            "doxiciclin",
+            "minocyclin",
+            "minociclin",
+        ],
+        cross_reactive: &[],
+    },
+    DrugClass {
+        name: "NSAIDs",
+        atc_prefixes: &["M01A", "N02BA"],
+        terms: &[
+            "nsaid",
+            "antinfiammatori non steroidei",
+            "aspirin",
+            "acido acetilsalicilico",
+            "acetylsalicylic acid",
+            "ibuprofen",
+            "ketoprofen",
+            "dexketoprofen",
+            "diclofenac",
+            "naproxen",
+            "ketorolac",
+            "nimesulide",
+            "indomethacin",
+            "indometacina",
+            "piroxicam",
+            "meloxicam",
+            "celecoxib",
+            "etoricoxib",
+        ],
+        cross_reactive: &[],
+    },
+    DrugClass {
+        name: "Opioids",
+        atc_prefixes: &["N02A"],
+        terms: &[
+            "opioid",
+            "oppioid",
+            "morphine",
+            "morfina",
+            "codeine",
+            "codeina",
+            "tramadol",
+            "oxycodone",
+            "ossicodone",
+            "fentanyl",
+            "tapentadol",
+        ],
+        cross_reactive: &[],
+    },
+    DrugClass {
+        name: "ACE inhibitors",
+        atc_prefixes: &["C09A", "C09B"],
+        terms: &[
+            "ace inhibitor",
+            "ace inibitor",
+            "ramipril",
+            "enalapril",
+            "lisinopril",
+            "perindopril",
+            "captopril",
+            "zofenopril",
+            "quinapril",
+        ],
+        cross_reactive: &[],
+    },
+    DrugClass {
+        name: "Iodinated contrast media",
+        atc_prefixes: &["V08A"],
+        terms: &[
+            "mezzo di contrasto iodato",
+            "mezzi di contrasto iodati",
+            "iodinated contrast",
+            "iohexol",
+            "ioexolo",
+            "iopamidol",
+            "iodixanol",
+            "iomeprol",
+        ],
+        cross_reactive: &[],
+    },
+];
+
+/// Drug being prescribed
+#[derive(Debug, Clone, Copy)]
+pub struct PrescribedDrug<'a> {
+    pub name: &'a str,
+    pub generic_name: Option<&'a str>,
+    pub atc_code: Option<&'a str>,
+}
+
+/// Alerts for every active drug allergy the drug matches, in the order of
+/// `allergies` (most severe first when loaded by `PatientAllergyService`)
+///
+/// Food and environmental allergies are not checked.
+pub fn check_drug_allergies(
+    drug: PrescribedDrug<'_>,
+    allergies: &[PatientAllergyResponse],
+) -> Vec<DrugAllergyAlert> {
+    allergies
+        .iter()
+        .filter(|a| matches!(a.category.as_str(), "DRUG" | "OTHER"))
+        .filter_map(|allergy| {
+            let (match_type, drug_class) = match_allergy(&allergy.substance, drug)?;
+            let message = match (match_type, drug_class) {
+                (AllergyMatchType::Substance, _) => {
+                    format!(
+                        "{} is recorded as an allergy of the patient",
+                        allergy.substance
+                    )
+                }
+                (AllergyMatchType::Class, Some(class)) => format!(
+                    "{} belongs to the same class as {} ({})",
+                    drug.name, allergy.substance, class
+                ),
+                (_, class) => format!(
+                    "{} ({}) may cross-react with {}",
+                    drug.name,
+                    class.unwrap_or("related class"),
+                    allergy.substance
+                ),
+            };
+
+            Some(DrugAllergyAlert {
+                allergy_id: allergy.id,
+                substance: allergy.substance.clone(),
+                severity: allergy.severity.clone(),
+                reaction: allergy.reaction.clone(),
+                match_type,
+                drug_class: drug_class.map(str::to_string),
+                message,
+            })
+        })
+        .collect()
+}
+
+/// How a drug matches one allergy substance, with the class of the drug for
+/// class and cross-reactivity matches
+fn match_allergy(
+    substance: &str,
+    drug: PrescribedDrug<'_>,
+) -> Option<(AllergyMatchType, Option<&'static str>)> {
+    let substance = normalize(substance);
+    if substance.is_empty() {
+        return None;
+    }
+
+    let drug_names: Vec<String> = std::iter::once(drug.name)
+        .chain(drug.generic_name)
+        .map(normalize)
+        .filter(|n| !n.is_empty())
+        .collect();
+
+    if drug_names
+        .iter()
+        .any(|name| names_overlap(&substance, name))
+    {
+        return Some((AllergyMatchType::Substance, None));
+    }
+
+    let allergen_classes: Vec<&DrugClass> = DRUG_CLASSES
+        .iter()
+        .filter(|class| mentions_class(&substance, class))
+        .collect();
+    if allergen_classes.is_empty() {
+        return None;
+    }
+
+    let drug_classes: Vec<&DrugClass> = DRUG_CLASSES
+        .iter()
+        .filter(|class| {
+            drug.atc_code.is_some_and(|atc| {
+                let atc = atc.trim().to_uppercase();
+                class.atc_prefixes.iter().any(|p| atc.starts_with(p))
+            }) || drug_names.iter().any(|name| mentions_class(name, class))
+        })
+        .collect();
+
+    if let Some(class) = drug_classes
+        .iter()
+        .find(|dc| allergen_classes.iter().any(|ac| ac.name == dc.name))
+    {
+        return Some((AllergyMatchType::Class, Some(class.name)));
+    }
+
+    drug_classes
+        .iter()
+        .find(|dc| {
+            allergen_classes
+                .iter()
+                .any(|ac| ac.cross_reactive.contains(&dc.name))
+        })
+        .map(|class| (AllergyMatchType::CrossReactivity, Some(class.name)))
+}
+
+/// Lowercase words separated by single spaces, accents folded
+fn normalize(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            'à' | 'á' | 'À' | 'Á' => 'a',
+            'è' | 'é' | 'È' | 'É' => 'e',
+            'ì' | 'í' | 'Ì' | 'Í' => 'i',
+            'ò' | 'ó' | 'Ò' | 'Ó' => 'o',
+            'ù' | 'ú' | 'Ù' | 'Ú' => 'u',
+            c if c.is_alphanumeric() => c.to_ascii_lowercase(),
+            _ => ' ',
+        })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Whether one name contains the other as whole words
+///
+/// Very short names (under 4 letters) only match exactly, so an allergy
+/// recorded as "ASA" does not flag every drug containing the word.
+fn names_overlap(a: &str, b: &str) -> bool {
+    if a.len() < 4 || b.len() < 4 {
+        return a == b;
+    }
+    contains_words(a, b) || contains_words(b, a)
+}
+
+fn contains_words(haystack: &str, needle: &str) -> bool {
+    format!(" {} ", haystack).contains(&format!(" {} ", needle))
+}
+
+fn mentions_class(text: &str, class: &DrugClass) -> bool {
+    class.terms.iter().any(|term| {
+        if term.contains(' ') {
+            contains_words(text, term)
+        } else {
+            text.split(' ').any(|word| word.starts_with(term))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    fn allergy(category: &str, substance: &str) -> PatientAllergyResponse {
+        PatientAllergyResponse {
+            id: Uuid::new_v4(),
+            patient_id: Uuid::new_v4(),
+            allergy_type: "ALLERGY".to_string(),
+            category: category.to_string(),
+            substance: substance.to_string(),
+            reaction: Some("Orticaria".to_string()),
+            severity: Some("SEVERE".to_string()),
+            onset_date: None,
+            status: "ACTIVE".to_string(),
+            notes: None,
+            created_by: Uuid::new_v4(),
+            created_at: Utc::now(),
+            updated_by: None,
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn drug<'a>(
+        name: &'a str,
+        generic: Option<&'a str>,
+        atc: Option<&'a str>,
+    ) -> PrescribedDrug<'a> {
+        PrescribedDrug {
+            name,
+            generic_name: generic,
+            atc_code: atc,
+        }
+    }
+
+    #[test]
+    fn test_substance_match_ignores_case_accents_and_combinations() {
+        let allergies = vec![allergy("DRUG", "Amoxicillina")];
+        let alerts = check_drug_allergies(
+            drug("Augmentin", Some("AMOXICILLINA/ACIDO CLAVULANICO"), None),
+            &allergies,
+        );
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].match_type, AllergyMatchType::Substance);
+        assert_eq!(alerts[0].allergy_id, allergies[0].id);
+    }
+
+    #[test]
+    fn test_class_match_by_atc_code_and_by_name() {
+        let allergies = vec![allergy("DRUG", "Penicillina")];
+
+        let by_atc = check_drug_allergies(drug("Zimox", None, Some("J01CA04")), &allergies);
+        assert_eq!(by_atc.len(), 1);
+        assert_eq!(by_atc[0].match_type, AllergyMatchType::Class);
+        assert_eq!(by_atc[0].drug_class.as_deref(), Some("Penicillins"));
+
+        let by_name = check_drug_allergies(drug("Amoxicillin 1g", None, None), &allergies);
+        assert_eq!(by_name[0].match_type, AllergyMatchType::Class);
+    }
+
+    #[test]
+    fn test_cross_reactivity_and_non_matches() {
+        let allergies = vec![allergy("DRUG", "Penicillin")];
+        let alerts = check_drug_allergies(drug("Rocefin", Some("Ceftriaxone"), None), &allergies);
+        assert_eq!(alerts[0].match_type, AllergyMatchType::CrossReactivity);
+        assert_eq!(alerts[0].drug_class.as_deref(), Some("Cephalosporins"));
+
+        assert!(
+            check_drug_allergies(drug("Ramipril", None, Some("C09AA05")), &allergies).is_empty()
+        );
+
+        let nsaid = vec![allergy("DRUG", "FANS - ibuprofene")];
+        let alerts = check_drug_allergies(drug("Aulin", Some("Nimesulide"), None), &nsaid);
+        assert_eq!(alerts[0].match_type, AllergyMatchType::Class);
+        assert_eq!(alerts[0].drug_class.as_deref(), Some("NSAIDs"));
+    }
+
+    #[test]
+    fn test_food_allergies_and_short_names_do_not_match() {
+        let allergies = vec![allergy("FOOD", "Penicillina"), allergy("DRUG", "ASA")];
+        assert!(check_drug_allergies(drug("Amoxicillina", None, None), &allergies).is_empty());
+        assert!(
+            check_drug_allergies(drug("Asacol", Some("Mesalazina"), None), &allergies).is_empty()
+        );
+    }
+}
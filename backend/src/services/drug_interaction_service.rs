@@ -16,6 +16,7 @@ use sqlx::PgPool;
 use strsim::normalized_damerau_levenshtein;
 use uuid::Uuid;
 
+use crate::db::rls::apply_rls_context;
 use crate::utils::encryption::EncryptionKey;
 
 /// Severity levels for drug interactions
@@ -429,15 +430,8 @@ impl DrugInteractionService {
         // Start transaction for RLS context
         let mut tx = pool.begin().await.context("Failed to begin transaction")?;
 
-        // Set RLS context using set_config() for parameterized queries
-        sqlx::query("SELECT set_config('app.current_user_id', $1, true)")
-            .bind(user_id.to_string())
-            .execute(&mut *tx)
-            .await?;
-        sqlx::query("SELECT set_config('app.current_user_role', $1, true)")
-            .bind(role)
-            .execute(&mut *tx)
-            .await?;
+        // Set RLS context
+        apply_rls_context(&mut tx, user_id, role).await?;
 
         // Step 1: Fetch patient's active prescriptions with encrypted data
         let prescriptions = sqlx::query!(
@@ -697,15 +691,8 @@ impl DrugInteractionService {
         // Start transaction for RLS context
         let mut tx = pool.begin().await.context("Failed to begin transaction")?;
 
-        // Set RLS context using set_config() for parameterized queries
-        sqlx::query("SELECT set_config('app.current_user_id', $1, true)")
-            .bind(user_id.to_string())
-            .execute(&mut *tx)
-            .await?;
-        sqlx::query("SELECT set_config('app.current_user_role', $1, true)")
-            .bind(role)
-            .execute(&mut *tx)
-            .await?;
+        // Set RLS context
+        apply_rls_context(&mut tx, user_id, role).await?;
 
         // Fetch patient's active prescriptions
         let prescriptions = sqlx::query!(
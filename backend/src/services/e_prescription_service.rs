@@ -0,0 +1,655 @@
+/*!
+ * E-Prescription Service
+ *
+ * Submits prescriptions to Sistema TS as dematerialized prescriptions and
+ * tracks their NRE:
+ * - submission stores the NRE on the prescription (`e_prescription_id`)
+ *   with status ISSUED, or REJECTED with the Sistema TS errors
+ * - status callbacks move an open NRE forward (taken in charge, dispensed,
+ *   expired, cancelled at the pharmacy)
+ * - the prescriber can cancel an NRE that is still open
+ *
+ * Every step is kept in `e_prescription_events`; the authentication code
+ * needed to cancel the NRE is stored encrypted on the ISSUED event. Calls to
+ * Sistema TS happen outside of any transaction.
+ */
+
+use crate::{
+    db::rls::{apply_rls_context, SYSTEM_ROLE, SYSTEM_USER_ID},
+    models::{
+        EPrescriptionCallback, EPrescriptionEvent, EPrescriptionEventSource, EPrescriptionResponse,
+        EPrescriptionStatus, NrePromemoria, PrescriptionResponse, PrescriptionStatus,
+        SubmitEPrescriptionRequest,
+    },
+    services::{
+        sistema_ts_client::{NreItem, NreSubmission, SistemaTsOutcome},
+        PrescriptionService, SistemaTsClient,
+    },
+    utils::{encryption::EncryptionKey, AppError, Result},
+};
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Packages a single dematerialized prescription may carry
+const MAX_PACKAGES: i32 = 6;
+
+const EVENT_COLUMNS: &str = r#"
+    id, prescription_id, nre, status, source, detail, aic_code, exemption_code,
+    occurred_at, created_by, created_at
+"#;
+
+/// E-prescription state of a prescription row
+#[derive(sqlx::FromRow)]
+struct EPrescriptionRow {
+    id: Uuid,
+    patient_id: Uuid,
+    e_prescription_id: Option<String>,
+    e_prescription_sent_at: Option<DateTime<Utc>>,
+    e_prescription_status: Option<String>,
+}
+
+impl EPrescriptionRow {
+    fn status(&self) -> Result<Option<EPrescriptionStatus>> {
+        self.e_prescription_status
+            .as_deref()
+            .map(|s| {
+                EPrescriptionStatus::from_db(s).ok_or_else(|| {
+                    AppError::Internal(format!("Unknown e-prescription status {}", s))
+                })
+            })
+            .transpose()
+    }
+}
+
+/// Event to record
+struct NewEvent<'a> {
+    prescription_id: Uuid,
+    patient_id: Uuid,
+    nre: Option<&'a str>,
+    status: EPrescriptionStatus,
+    source: EPrescriptionEventSource,
+    detail: Option<&'a str>,
+    auth_code: Option<&'a str>,
+    aic_code: Option<&'a str>,
+    exemption_code: Option<&'a str>,
+    occurred_at: DateTime<Utc>,
+    created_by: Option<Uuid>,
+}
+
+/// E-prescription service
+pub struct EPrescriptionService {
+    pool: PgPool,
+    encryption_key: EncryptionKey,
+}
+
+impl EPrescriptionService {
+    /// Create a new e-prescription service
+    pub fn new(pool: PgPool, encryption_key: EncryptionKey) -> Self {
+        Self {
+            pool,
+            encryption_key,
+        }
+    }
+
+    /// Submit a prescription to Sistema TS
+    ///
+    /// The prescription must be ACTIVE and have no open NRE; the patient
+    /// needs a fiscal code. The AIC code defaults to the one of the active
+    /// medication with the prescription's name. A refusal by Sistema TS is
+    /// recorded and returned with status REJECTED; the prescription can then
+    /// be corrected and submitted again.
+    pub async fn submit(
+        &self,
+        client: &SistemaTsClient,
+        prescription_id: Uuid,
+        req: &SubmitEPrescriptionRequest,
+        user_id: Uuid,
+        role: &str,
+    ) -> Result<EPrescriptionResponse> {
+        let prescription = self
+            .get_prescription(prescription_id, user_id, role)
+            .await?;
+        if prescription.status != PrescriptionStatus::Active {
+            return Err(AppError::Conflict(format!(
+                "Prescription {} is not active",
+                prescription_id
+            )));
+        }
+
+        let quantity = prescription.quantity.unwrap_or(1);
+        if !(1..=MAX_PACKAGES).contains(&quantity) {
+            return Err(AppError::Validation(format!(
+                "A dematerialized prescription carries 1 to {} packages",
+                MAX_PACKAGES
+            )));
+        }
+
+        let mut tx = self.begin(user_id, role).await?;
+        let row = self.find_row(&mut tx, prescription_id).await?;
+        if row.status()?.is_some_and(|s| s.is_open()) {
+            return Err(AppError::Conflict(format!(
+                "Prescription {} already has an open NRE",
+                prescription_id
+            )));
+        }
+        let fiscal_code = self.patient_fiscal_code(&mut tx, row.patient_id).await?;
+        let aic_code = match &req.aic_code {
+            Some(aic_code) => aic_code.trim().to_string(),
+            None => self.medication_aic_code(&mut tx, &prescription).await?,
+        };
+        if !aic_code.chars().all(|c| c.is_ascii_digit()) {
+            return Err(AppError::Validation(
+                "AIC code must contain digits only".to_string(),
+            ));
+        }
+        tx.commit().await?;
+
+        let exemption_code = req
+            .exemption_code
+            .as_deref()
+            .map(|c| c.trim().to_uppercase());
+        let description = format!("{} {}", prescription.medication_name, prescription.dosage);
+        let submission = NreSubmission {
+            patient_fiscal_code: &fiscal_code,
+            exemption_code: exemption_code.as_deref(),
+            compiled_at: Utc::now(),
+            items: vec![NreItem {
+                aic_code: &aic_code,
+                description: &description,
+                quantity,
+            }],
+        };
+
+        let outcome = client
+            .submit(&submission)
+            .await
+            .map_err(|e| AppError::Internal(format!("Sistema TS submission failed: {}", e)))?;
+
+        let mut tx = self.begin(user_id, role).await?;
+        let (nre, status, auth_code, detail) = match &outcome {
+            SistemaTsOutcome::Accepted(receipt) => (
+                Some(receipt.nre.as_str()),
+                EPrescriptionStatus::Issued,
+                Some(receipt.auth_code.as_str()),
+                None,
+            ),
+            SistemaTsOutcome::Rejected(errors) => (
+                None,
+                EPrescriptionStatus::Rejected,
+                None,
+                Some(errors.join("; ")),
+            ),
+        };
+
+        sqlx::query(
+            r#"
+            UPDATE prescriptions
+            SET e_prescription_id = $2,
+                e_prescription_sent_at = NOW(),
+                e_prescription_status = $3,
+                updated_by = $4
+            WHERE id = $1
+            "#,
+        )
+        .bind(prescription_id)
+        .bind(nre)
+        .bind(status.as_str())
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+        self.record_event(
+            &mut tx,
+            NewEvent {
+                prescription_id,
+                patient_id: row.patient_id,
+                nre,
+                status,
+                source: EPrescriptionEventSource::Submission,
+                detail: detail.as_deref(),
+                auth_code,
+                aic_code: Some(&aic_code),
+                exemption_code: exemption_code.as_deref(),
+                occurred_at: submission.compiled_at,
+                created_by: Some(user_id),
+            },
+        )
+        .await?;
+
+        let response = self.load_response(&mut tx, prescription_id).await?;
+        tx.commit().await?;
+
+        match nre {
+            Some(nre) => info!(
+                "Prescription {} issued as NRE {} by {}",
+                prescription_id, nre, user_id
+            ),
+            None => warn!(
+                "Sistema TS rejected prescription {} submitted by {}",
+                prescription_id, user_id
+            ),
+        }
+
+        Ok(response)
+    }
+
+    /// Cancel the open NRE of a prescription at Sistema TS
+    ///
+    /// Fails with Conflict if the NRE has already been dispensed, cancelled
+    /// or has expired, and with BadRequest if Sistema TS refuses.
+    pub async fn cancel(
+        &self,
+        client: &SistemaTsClient,
+        prescription_id: Uuid,
+        user_id: Uuid,
+        role: &str,
+    ) -> Result<EPrescriptionResponse> {
+        let mut tx = self.begin(user_id, role).await?;
+        let row = self.find_row(&mut tx, prescription_id).await?;
+        let nre = self.open_nre(&row)?;
+
+        let auth_code: Option<String> = sqlx::query_scalar(
+            r#"
+            SELECT auth_code
+            FROM e_prescription_events
+            WHERE prescription_id = $1 AND nre = $2 AND status = 'ISSUED'
+            ORDER BY created_at DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(prescription_id)
+        .bind(&nre)
+        .fetch_optional(&mut *tx)
+        .await?
+        .flatten();
+        tx.commit().await?;
+
+        let auth_code = auth_code.ok_or_else(|| {
+            AppError::Internal(format!("No authentication code stored for NRE {}", nre))
+        })?;
+        let auth_code = self.decrypt(&auth_code)?;
+
+        let outcome = client
+            .cancel(&nre, &auth_code)
+            .await
+            .map_err(|e| AppError::Internal(format!("Sistema TS cancellation failed: {}", e)))?;
+        if let SistemaTsOutcome::Rejected(errors) = outcome {
+            return Err(AppError::BadRequest(format!(
+                "Sistema TS refused to cancel NRE {}: {}",
+                nre,
+                errors.join("; ")
+            )));
+        }
+
+        let mut tx = self.begin(user_id, role).await?;
+        self.set_status(&mut tx, prescription_id, EPrescriptionStatus::Cancelled)
+            .await?;
+        self.record_event(
+            &mut tx,
+            NewEvent {
+                prescription_id,
+                patient_id: row.patient_id,
+                nre: Some(&nre),
+                status: EPrescriptionStatus::Cancelled,
+                source: EPrescriptionEventSource::Cancellation,
+                detail: None,
+                auth_code: None,
+                aic_code: None,
+                exemption_code: None,
+                occurred_at: Utc::now(),
+                created_by: Some(user_id),
+            },
+        )
+        .await?;
+
+        let response = self.load_response(&mut tx, prescription_id).await?;
+        tx.commit().await?;
+
+        info!(
+            "NRE {} of prescription {} cancelled by {}",
+            nre, prescription_id, user_id
+        );
+
+        Ok(response)
+    }
+
+    /// Apply a Sistema TS status callback
+    ///
+    /// Runs as the system user. Repeating the current status is a no-op, so
+    /// redelivered callbacks are harmless; any other change an NRE cannot
+    /// make (see `EPrescriptionStatus::can_transition_to`) fails with
+    /// Conflict.
+    pub async fn apply_callback(
+        &self,
+        callback: &EPrescriptionCallback,
+    ) -> Result<EPrescriptionResponse> {
+        let mut tx = self.begin(SYSTEM_USER_ID, SYSTEM_ROLE).await?;
+
+        let row = sqlx::query_as::<_, EPrescriptionRow>(
+            r#"
+            SELECT id, patient_id, e_prescription_id, e_prescription_sent_at, e_prescription_status
+            FROM prescriptions
+            WHERE e_prescription_id = $1
+            FOR UPDATE
+            "#,
+        )
+        .bind(callback.nre.trim())
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("NRE {} not found", callback.nre)))?;
+
+        let current = row.status()?.ok_or_else(|| {
+            AppError::Internal(format!("Prescription {} has an NRE but no status", row.id))
+        })?;
+        let next = callback.status;
+
+        if current != next {
+            if !current.can_transition_to(next) {
+                return Err(AppError::Conflict(format!(
+                    "NRE {} cannot move from {} to {}",
+                    callback.nre,
+                    current.as_str(),
+                    next.as_str()
+                )));
+            }
+
+            self.set_status(&mut tx, row.id, next).await?;
+            self.record_event(
+                &mut tx,
+                NewEvent {
+                    prescription_id: row.id,
+                    patient_id: row.patient_id,
+                    nre: row.e_prescription_id.as_deref(),
+                    status: next,
+                    source: EPrescriptionEventSource::Callback,
+                    detail: callback.detail.as_deref(),
+                    auth_code: None,
+                    aic_code: None,
+                    exemption_code: None,
+                    occurred_at: callback.occurred_at.unwrap_or_else(Utc::now),
+                    created_by: None,
+                },
+            )
+            .await?;
+
+            info!(
+                "NRE {} of prescription {} moved from {} to {}",
+                callback.nre,
+                row.id,
+                current.as_str(),
+                next.as_str()
+            );
+        }
+
+        let response = self.load_response(&mut tx, row.id).await?;
+        tx.commit().await?;
+
+        Ok(response)
+    }
+
+    /// Get the e-prescription state of a prescription with its events
+    pub async fn get_status(
+        &self,
+        prescription_id: Uuid,
+        user_id: Uuid,
+        role: &str,
+    ) -> Result<EPrescriptionResponse> {
+        let mut tx = self.begin(user_id, role).await?;
+        let response = self.load_response(&mut tx, prescription_id).await?;
+        tx.commit().await?;
+
+        Ok(response)
+    }
+
+    /// Everything the promemoria of a prescription's NRE prints
+    ///
+    /// Fails with Conflict unless the NRE is still open: a dispensed,
+    /// cancelled or expired NRE cannot be collected any more.
+    pub async fn prepare_promemoria(
+        &self,
+        prescription_id: Uuid,
+        user_id: Uuid,
+        role: &str,
+    ) -> Result<NrePromemoria> {
+        let prescription = self
+            .get_prescription(prescription_id, user_id, role)
+            .await?;
+
+        let mut tx = self.begin(user_id, role).await?;
+        let row = self.find_row(&mut tx, prescription_id).await?;
+        let nre = self.open_nre(&row)?;
+
+        let (aic_code, exemption_code): (Option<String>, Option<String>) = sqlx::query_as(
+            r#"
+            SELECT aic_code, exemption_code
+            FROM e_prescription_events
+            WHERE prescription_id = $1 AND nre = $2 AND status = 'ISSUED'
+            ORDER BY created_at DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(prescription_id)
+        .bind(&nre)
+        .fetch_optional(&mut *tx)
+        .await?
+        .unwrap_or_default();
+
+        let patient_fiscal_code = self.patient_fiscal_code(&mut tx, row.patient_id).await?;
+        tx.commit().await?;
+
+        Ok(NrePromemoria {
+            issued_at: row.e_prescription_sent_at.unwrap_or_else(Utc::now),
+            quantity: prescription.quantity.unwrap_or(1),
+            prescription,
+            nre,
+            patient_fiscal_code,
+            aic_code,
+            exemption_code,
+            prescriber_fiscal_code: None,
+        })
+    }
+
+    async fn begin(&self, user_id: Uuid, role: &str) -> Result<Transaction<'static, Postgres>> {
+        let mut tx = self.pool.begin().await?;
+        apply_rls_context(&mut tx, user_id, role).await?;
+        Ok(tx)
+    }
+
+    async fn get_prescription(
+        &self,
+        prescription_id: Uuid,
+        user_id: Uuid,
+        role: &str,
+    ) -> Result<PrescriptionResponse> {
+        PrescriptionService::new(self.pool.clone(), self.encryption_key.clone())
+            .get_prescription(prescription_id, user_id, role)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?
+            .ok_or_else(|| {
+                AppError::NotFound(format!("Prescription {} not found", prescription_id))
+            })
+    }
+
+    async fn find_row(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        prescription_id: Uuid,
+    ) -> Result<EPrescriptionRow> {
+        sqlx::query_as::<_, EPrescriptionRow>(
+            r#"
+            SELECT id, patient_id, e_prescription_id, e_prescription_sent_at, e_prescription_status
+            FROM prescriptions
+            WHERE id = $1
+            "#,
+        )
+        .bind(prescription_id)
+        .fetch_optional(&mut **tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Prescription {} not found", prescription_id)))
+    }
+
+    /// NRE of a prescription, which must still be open
+    fn open_nre(&self, row: &EPrescriptionRow) -> Result<String> {
+        match (&row.e_prescription_id, row.status()?) {
+            (Some(nre), Some(status)) if status.is_open() => Ok(nre.clone()),
+            (Some(nre), Some(status)) => Err(AppError::Conflict(format!(
+                "NRE {} is {}",
+                nre,
+                status.as_str()
+            ))),
+            _ => Err(AppError::Conflict(format!(
+                "Prescription {} has not been issued as an e-prescription",
+                row.id
+            ))),
+        }
+    }
+
+    async fn load_response(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        prescription_id: Uuid,
+    ) -> Result<EPrescriptionResponse> {
+        let row = self.find_row(tx, prescription_id).await?;
+
+        let mut events = sqlx::query_as::<_, EPrescriptionEvent>(&format!(
+            r#"
+            SELECT {}
+            FROM e_prescription_events
+            WHERE prescription_id = $1
+            ORDER BY created_at DESC
+            "#,
+            EVENT_COLUMNS
+        ))
+        .bind(prescription_id)
+        .fetch_all(&mut **tx)
+        .await?;
+
+        for event in &mut events {
+            if let Some(detail) = &event.detail {
+                event.detail = Some(self.decrypt(detail)?);
+            }
+        }
+
+        Ok(EPrescriptionResponse {
+            prescription_id,
+            nre: row.e_prescription_id,
+            status: row.e_prescription_status,
+            sent_at: row.e_prescription_sent_at,
+            events,
+        })
+    }
+
+    async fn set_status(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        prescription_id: Uuid,
+        status: EPrescriptionStatus,
+    ) -> Result<()> {
+        sqlx::query("UPDATE prescriptions SET e_prescription_status = $2 WHERE id = $1")
+            .bind(prescription_id)
+            .bind(status.as_str())
+            .execute(&mut **tx)
+            .await?;
+        Ok(())
+    }
+
+    async fn record_event(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        event: NewEvent<'_>,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO e_prescription_events (
+                prescription_id, patient_id, nre, status, source, detail, auth_code,
+                aic_code, exemption_code, occurred_at, created_by
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            "#,
+        )
+        .bind(event.prescription_id)
+        .bind(event.patient_id)
+        .bind(event.nre)
+        .bind(event.status.as_str())
+        .bind(event.source.as_str())
+        .bind(event.detail.map(|d| self.encrypt(d)).transpose()?)
+        .bind(event.auth_code.map(|c| self.encrypt(c)).transpose()?)
+        .bind(event.aic_code)
+        .bind(event.exemption_code)
+        .bind(event.occurred_at)
+        .bind(event.created_by)
+        .execute(&mut **tx)
+        .await?;
+        Ok(())
+    }
+
+    /// Decrypted fiscal code of the patient, which Sistema TS requires
+    async fn patient_fiscal_code(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        patient_id: Uuid,
+    ) -> Result<String> {
+        let fiscal_code: Option<String> =
+            sqlx::query_scalar("SELECT fiscal_code FROM patients WHERE id = $1")
+                .bind(patient_id)
+                .fetch_optional(&mut **tx)
+                .await?
+                .ok_or_else(|| AppError::NotFound(format!("Patient {} not found", patient_id)))?;
+
+        match fiscal_code {
+            Some(encrypted) => Ok(self.decrypt(&encrypted)?.trim().to_uppercase()),
+            None => Err(AppError::BadRequest(
+                "The patient has no fiscal code, required for e-prescriptions".to_string(),
+            )),
+        }
+    }
+
+    /// AIC code of the active medication named like the prescription
+    async fn medication_aic_code(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        prescription: &PrescriptionResponse,
+    ) -> Result<String> {
+        let aic_code: Option<String> = sqlx::query_scalar(
+            r#"
+            SELECT aic_code
+            FROM medications
+            WHERE is_active = true
+              AND aic_code IS NOT NULL
+              AND (LOWER(name) = LOWER($1) OR LOWER(generic_name) = LOWER($2))
+            ORDER BY (LOWER(name) = LOWER($1)) DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(&prescription.medication_name)
+        .bind(
+            prescription
+                .generic_name
+                .as_deref()
+                .unwrap_or(&prescription.medication_name),
+        )
+        .fetch_optional(&mut **tx)
+        .await?;
+
+        aic_code.ok_or_else(|| {
+            AppError::BadRequest(format!(
+                "No AIC code found for {}; pass aic_code",
+                prescription.medication_name
+            ))
+        })
+    }
+
+    fn encrypt(&self, value: &str) -> Result<String> {
+        self.encryption_key.encrypt(value).map_err(|e| {
+            AppError::Internal(format!("Failed to encrypt e-prescription data: {}", e))
+        })
+    }
+
+    fn decrypt(&self, value: &str) -> Result<String> {
+        self.encryption_key.decrypt(value).map_err(|e| {
+            AppError::Internal(format!("Failed to decrypt e-prescription data: {}", e))
+        })
+    }
+}
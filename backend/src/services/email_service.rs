@@ -127,15 +127,61 @@ impl EmailService {
             });
         }
 
-        let transport = self.transport.as_ref().ok_or_else(|| {
-            anyhow::anyhow!("Email transport not initialized")
-        })?;
-
         // Read the attachment file
         let attachment_data = tokio::fs::read(attachment_path)
             .await
             .context("Failed to read attachment file")?;
 
+        self.send_attachment(
+            to_email,
+            to_name,
+            subject,
+            body_text,
+            body_html,
+            attachment_data,
+            attachment_name,
+            "application/pdf",
+        )
+        .await
+    }
+
+    /// Send an email with an in-memory attachment
+    ///
+    /// # Arguments
+    /// * `to_email` - Recipient email address
+    /// * `to_name` - Recipient name
+    /// * `subject` - Email subject line
+    /// * `body_text` - Plain text body
+    /// * `body_html` - Optional HTML body
+    /// * `attachment_data` - Attachment content
+    /// * `attachment_name` - Filename for the attachment
+    /// * `content_type` - MIME type of the attachment
+    ///
+    /// # Returns
+    /// EmailResult indicating success or failure
+    #[allow(clippy::too_many_arguments)]
+    pub async fn send_attachment(
+        &self,
+        to_email: &str,
+        to_name: &str,
+        subject: &str,
+        body_text: &str,
+        body_html: Option<&str>,
+        attachment_data: Vec<u8>,
+        attachment_name: &str,
+        content_type: &str,
+    ) -> Result<EmailResult> {
+        if !self.enabled {
+            return Ok(EmailResult {
+                success: false,
+                message: "Email service is not configured".to_string(),
+            });
+        }
+
+        let transport = self.transport.as_ref().ok_or_else(|| {
+            anyhow::anyhow!("Email transport not initialized")
+        })?;
+
         // Build the from address
         let from = format!("{} <{}>", self.from_name, self.from_email)
             .parse()
@@ -147,8 +193,10 @@ impl EmailService {
             .context("Invalid recipient address")?;
 
         // Create the attachment
-        let attachment = Attachment::new(attachment_name.to_string())
-            .body(attachment_data, ContentType::parse("application/pdf").unwrap());
+        let attachment = Attachment::new(attachment_name.to_string()).body(
+            attachment_data,
+            ContentType::parse(content_type).context("Invalid attachment content type")?,
+        );
 
         // Build the message body
         let body = if let Some(html) = body_html {
@@ -282,6 +330,41 @@ impl EmailService {
     }
 }
 
+/// Practice branding applied to outgoing HTML emails
+#[derive(Debug, Clone)]
+pub struct EmailBranding {
+    /// Color for headings and header rules (#RRGGBB)
+    pub primary_color: String,
+    /// Color for highlighted text (#RRGGBB)
+    pub accent_color: String,
+    /// Absolute URL of the header banner image (None = no banner)
+    pub header_image_url: Option<String>,
+}
+
+impl Default for EmailBranding {
+    fn default() -> Self {
+        Self {
+            primary_color: "#2563eb".to_string(),
+            accent_color: "#1e40af".to_string(),
+            header_image_url: None,
+        }
+    }
+}
+
+impl EmailBranding {
+    /// HTML snippet for the header banner (empty if no banner is configured)
+    pub fn header_image_html(&self, alt: &str) -> String {
+        match &self.header_image_url {
+            Some(url) => format!(
+                r#"<img src="{}" alt="{}" style="display: block; max-width: 100%; height: auto; margin-bottom: 15px;">"#,
+                url.replace('"', "&quot;"),
+                alt.replace('"', "&quot;")
+            ),
+            None => String::new(),
+        }
+    }
+}
+
 /// Generate a professional email body for document delivery
 ///
 /// # Arguments
@@ -289,6 +372,7 @@ impl EmailService {
 /// * `document_title` - Title of the document
 /// * `doctor_name` - Name of the sending doctor
 /// * `practice_name` - Name of the medical practice
+/// * `branding` - Practice colors and header image
 ///
 /// # Returns
 /// Tuple of (plain_text, html) email body
@@ -297,6 +381,7 @@ pub fn generate_document_email_body(
     document_title: &str,
     doctor_name: &str,
     practice_name: &str,
+    branding: &EmailBranding,
 ) -> (String, String) {
     let plain_text = format!(
         r#"Dear {},
@@ -321,7 +406,7 @@ Best regards,
     <style>
         body {{ font-family: Arial, sans-serif; line-height: 1.6; color: #333; }}
         .container {{ max-width: 600px; margin: 0 auto; padding: 20px; }}
-        .header {{ border-bottom: 2px solid #2563eb; padding-bottom: 10px; margin-bottom: 20px; }}
+        .header {{ border-bottom: 2px solid {primary}; padding-bottom: 10px; margin-bottom: 20px; }}
         .content {{ margin-bottom: 30px; }}
         .footer {{ font-size: 12px; color: #666; border-top: 1px solid #ddd; padding-top: 15px; }}
         .confidentiality {{ background-color: #fef2f2; border: 1px solid #fecaca; padding: 15px; margin-top: 20px; border-radius: 4px; font-size: 11px; }}
@@ -330,16 +415,17 @@ Best regards,
 <body>
     <div class="container">
         <div class="header">
-            <h2 style="margin: 0; color: #2563eb;">{}</h2>
+            {header_image}
+            <h2 style="margin: 0; color: {primary};">{practice_name}</h2>
         </div>
         <div class="content">
-            <p>Dear {},</p>
+            <p>Dear {patient_name},</p>
             <p>Please find attached the following document:</p>
-            <p style="font-weight: bold; font-size: 16px; color: #1e40af;">{}</p>
-            <p>This document was generated by <strong>{}</strong>.</p>
+            <p style="font-weight: bold; font-size: 16px; color: {accent};">{document_title}</p>
+            <p>This document was generated by <strong>{doctor_name}</strong>.</p>
         </div>
         <div class="footer">
-            <p>Best regards,<br><strong>{}</strong><br>{}</p>
+            <p>Best regards,<br><strong>{doctor_name}</strong><br>{practice_name}</p>
             <div class="confidentiality">
                 <strong>CONFIDENTIALITY NOTICE:</strong> This email and any attachments contain confidential medical information intended only for the named recipient. If you have received this email in error, please notify the sender immediately and delete this message and any attachments. Unauthorized disclosure, copying, or distribution of this information is strictly prohibited.
             </div>
@@ -347,7 +433,13 @@ Best regards,
     </div>
 </body>
 </html>"#,
-        practice_name, patient_name, document_title, doctor_name, doctor_name, practice_name
+        primary = branding.primary_color,
+        accent = branding.accent_color,
+        header_image = branding.header_image_html(practice_name),
+        practice_name = practice_name,
+        patient_name = patient_name,
+        document_title = document_title,
+        doctor_name = doctor_name,
     );
 
     (plain_text, html)
@@ -364,6 +456,7 @@ mod tests {
             "Medical Certificate",
             "Dr. Smith",
             "DocPat Medical Practice",
+            &EmailBranding::default(),
         );
 
         assert!(plain.contains("John Doe"));
@@ -377,6 +470,30 @@ mod tests {
         assert!(html.contains("CONFIDENTIALITY NOTICE"));
     }
 
+    #[test]
+    fn test_email_body_applies_branding() {
+        let branding = EmailBranding {
+            primary_color: "#0f766e".to_string(),
+            accent_color: "#115e59".to_string(),
+            header_image_url: Some(
+                "https://studio.example.com/api/v1/branding/assets/email-header".to_string(),
+            ),
+        };
+
+        let (_, html) = generate_document_email_body(
+            "John Doe",
+            "Medical Certificate",
+            "Dr. Smith",
+            "DocPat Medical Practice",
+            &branding,
+        );
+
+        assert!(html.contains("#0f766e"));
+        assert!(html.contains("#115e59"));
+        assert!(html.contains("/api/v1/branding/assets/email-header"));
+        assert!(!html.contains("#2563eb"));
+    }
+
     #[test]
     fn test_disabled_email_service() {
         let service = EmailService::new(None).unwrap();
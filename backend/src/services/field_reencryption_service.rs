@@ -0,0 +1,211 @@
+/*!
+ * Field Re-encryption Service
+ *
+ * Background job moving lookup fields written before deterministic
+ * encryption existed (patient fiscal codes, insurance policy numbers) from
+ * randomized AES-GCM to AES-SIV (`EncryptionKey::encrypt_deterministic`).
+ * Both formats decrypt with `EncryptionKey::decrypt`, so readers do not
+ * care which one a row has.
+ *
+ * Active patients must have distinct fiscal codes once encrypted
+ * deterministically (`idx_patients_fiscal_code_unique`). A fiscal code
+ * already used by another active patient is left as it is and reported:
+ * the two records are duplicates to merge.
+ */
+
+use crate::db::rls::{apply_rls_context, include_trashed_records, SYSTEM_ROLE, SYSTEM_USER_ID};
+use crate::utils::{
+    encryption::{DeterministicField, EncryptionKey},
+    Result,
+};
+use sqlx::{PgPool, Postgres, Transaction};
+use tokio::time::{sleep, Duration};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// Pause between runs
+const POLL_INTERVAL_SECS: u64 = 3600;
+
+/// Rows re-encrypted per transaction
+const BATCH_SIZE: i64 = 200;
+
+/// Outcome of a re-encryption run
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReencryptionSummary {
+    pub fiscal_codes: u64,
+    pub policy_numbers: u64,
+    /// Fiscal codes left randomized because another active patient has them
+    pub fiscal_code_conflicts: u64,
+}
+
+/// Re-encryption of lookup fields
+pub struct FieldReencryptionService {
+    pool: PgPool,
+    encryption_key: EncryptionKey,
+}
+
+impl FieldReencryptionService {
+    /// Create a new re-encryption service
+    pub fn new(pool: PgPool, encryption_key: EncryptionKey) -> Self {
+        Self {
+            pool,
+            encryption_key,
+        }
+    }
+
+    /// Re-encrypt every fiscal code and policy number still randomized
+    ///
+    /// Rows are visited once per run in id order, so rows left as they are
+    /// (conflicts, values that cannot be decrypted) do not hold up the rest.
+    pub async fn run(&self) -> Result<ReencryptionSummary> {
+        let mut summary = ReencryptionSummary::default();
+
+        let mut cursor = Uuid::nil();
+        loop {
+            let mut tx = self.begin_system_tx().await?;
+            let rows: Vec<(Uuid, String)> = sqlx::query_as(
+                r#"
+                SELECT id, fiscal_code
+                FROM patients
+                WHERE id > $1 AND fiscal_code IS NOT NULL AND fiscal_code NOT LIKE 'siv:%'
+                  AND anonymized_at IS NULL AND merged_at IS NULL
+                ORDER BY id
+                LIMIT $2
+                "#,
+            )
+            .bind(cursor)
+            .bind(BATCH_SIZE)
+            .fetch_all(&mut *tx)
+            .await?;
+
+            for (id, encrypted) in &rows {
+                cursor = *id;
+                let Some(reencrypted) =
+                    self.reencrypt(DeterministicField::FiscalCode, *id, encrypted)
+                else {
+                    continue;
+                };
+
+                // Skip instead of failing on the unique index (same predicate)
+                let updated = sqlx::query(
+                    r#"
+                    UPDATE patients p
+                    SET fiscal_code = $2
+                    WHERE p.id = $1
+                      AND (p.deleted_at IS NOT NULL OR NOT EXISTS (
+                          SELECT 1 FROM patients o
+                          WHERE o.fiscal_code = $2 AND o.id <> p.id
+                            AND o.merged_at IS NULL AND o.deleted_at IS NULL
+                      ))
+                    "#,
+                )
+                .bind(id)
+                .bind(&reencrypted)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+
+                if updated == 0 {
+                    warn!(
+                        "Patient {} has the fiscal code of another active patient; merge them to encrypt it deterministically",
+                        id
+                    );
+                    summary.fiscal_code_conflicts += 1;
+                } else {
+                    summary.fiscal_codes += 1;
+                }
+            }
+
+            tx.commit().await?;
+            if (rows.len() as i64) < BATCH_SIZE {
+                break;
+            }
+        }
+
+        let mut cursor = Uuid::nil();
+        loop {
+            let mut tx = self.begin_system_tx().await?;
+            let rows: Vec<(Uuid, String)> = sqlx::query_as(
+                r#"
+                SELECT id, policy_number
+                FROM patient_insurance
+                WHERE id > $1 AND policy_number NOT LIKE 'siv:%'
+                ORDER BY id
+                LIMIT $2
+                "#,
+            )
+            .bind(cursor)
+            .bind(BATCH_SIZE)
+            .fetch_all(&mut *tx)
+            .await?;
+
+            for (id, encrypted) in &rows {
+                cursor = *id;
+                let Some(reencrypted) =
+                    self.reencrypt(DeterministicField::PolicyNumber, *id, encrypted)
+                else {
+                    continue;
+                };
+
+                sqlx::query("UPDATE patient_insurance SET policy_number = $2 WHERE id = $1")
+                    .bind(id)
+                    .bind(&reencrypted)
+                    .execute(&mut *tx)
+                    .await?;
+                summary.policy_numbers += 1;
+            }
+
+            tx.commit().await?;
+            if (rows.len() as i64) < BATCH_SIZE {
+                break;
+            }
+        }
+
+        Ok(summary)
+    }
+
+    /// Deterministic ciphertext of a randomized one; none (logged) when it
+    /// cannot be decrypted
+    fn reencrypt(&self, field: DeterministicField, id: Uuid, encrypted: &str) -> Option<String> {
+        let result = self
+            .encryption_key
+            .decrypt(encrypted)
+            .and_then(|plaintext| self.encryption_key.encrypt_deterministic(field, &plaintext));
+
+        match result {
+            Ok(reencrypted) => Some(reencrypted),
+            Err(e) => {
+                warn!("Cannot re-encrypt {} of {}: {}", field.as_str(), id, e);
+                None
+            }
+        }
+    }
+
+    async fn begin_system_tx(&self) -> Result<Transaction<'static, Postgres>> {
+        let mut tx = self.pool.begin().await?;
+        apply_rls_context(&mut tx, SYSTEM_USER_ID, SYSTEM_ROLE).await?;
+        include_trashed_records(&mut tx).await?;
+        Ok(tx)
+    }
+}
+
+/// Spawn the re-encryption job as a background task
+pub fn spawn_field_reencryption_job(pool: PgPool, encryption_key: EncryptionKey) {
+    let service = FieldReencryptionService::new(pool, encryption_key);
+
+    tokio::spawn(async move {
+        loop {
+            match service.run().await {
+                Ok(summary) if summary == ReencryptionSummary::default() => {}
+                Ok(summary) => info!(
+                    "Re-encrypted {} fiscal codes and {} policy numbers deterministically ({} fiscal codes shared by active patients)",
+                    summary.fiscal_codes, summary.policy_numbers, summary.fiscal_code_conflicts
+                ),
+                Err(e) => error!("Field re-encryption failed: {}", e),
+            }
+            sleep(Duration::from_secs(POLL_INTERVAL_SECS)).await;
+        }
+    });
+
+    info!("Field re-encryption job spawned as background task");
+}
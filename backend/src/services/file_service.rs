@@ -14,7 +14,9 @@ use crate::models::uploaded_file::{
     magic_bytes, FileValidationResult, FilePurpose, FilesFilter, ListFilesResponse,
     UploadedFile, UploadedFileResponse, MAX_FILE_SIZE, MAX_FILENAME_LENGTH,
     ALLOWED_IMAGE_MIME_TYPES, ALLOWED_LOGO_MIME_TYPES, ALLOWED_DOCUMENT_MIME_TYPES,
+    ALLOWED_PATIENT_PHOTO_MIME_TYPES, ALLOWED_VISIT_ATTACHMENT_MIME_TYPES,
 };
+use crate::utils::encryption::EncryptionKey;
 use anyhow::{anyhow, Context, Result};
 use sha2::{Digest, Sha256};
 use sqlx::PgPool;
@@ -61,6 +63,36 @@ impl FileUploadService {
             return Some("image/webp".to_string());
         }
 
+        // Check for WAV (RIFF....WAVE)
+        if content.len() >= 12
+            && content.starts_with(magic_bytes::WEBP_RIFF)
+            && &content[8..12] == magic_bytes::WAV_MARKER
+        {
+            return Some("audio/wav".to_string());
+        }
+
+        // Check for MP3 (ID3 tag, or an MPEG audio frame sync)
+        if content.starts_with(magic_bytes::MP3_ID3)
+            || (content[0] == 0xFF && matches!(content[1], 0xFB | 0xF3 | 0xF2))
+        {
+            return Some("audio/mpeg".to_string());
+        }
+
+        // Check for Ogg (OggS)
+        if content.starts_with(magic_bytes::OGG) {
+            return Some("audio/ogg".to_string());
+        }
+
+        // Check for WebM (EBML header); browsers record audio memos as WebM
+        if content.starts_with(magic_bytes::WEBM) {
+            return Some("audio/webm".to_string());
+        }
+
+        // Check for M4A/MP4 audio (....ftyp)
+        if content.len() >= 8 && &content[4..8] == magic_bytes::MP4_FTYP {
+            return Some("audio/mp4".to_string());
+        }
+
         // Check for PDF (%PDF)
         if content.starts_with(magic_bytes::PDF) {
             return Some("application/pdf".to_string());
@@ -137,10 +169,12 @@ impl FileUploadService {
 
         // Get allowed MIME types based on purpose
         let allowed_types: &[&str] = match purpose {
-            FilePurpose::Logo => ALLOWED_LOGO_MIME_TYPES,
+            FilePurpose::Logo | FilePurpose::Branding => ALLOWED_LOGO_MIME_TYPES,
             FilePurpose::Avatar => ALLOWED_IMAGE_MIME_TYPES,
-            FilePurpose::Document => ALLOWED_DOCUMENT_MIME_TYPES,
+            FilePurpose::Document | FilePurpose::PatientAttachment => ALLOWED_DOCUMENT_MIME_TYPES,
             FilePurpose::Attachment => ALLOWED_IMAGE_MIME_TYPES,
+            FilePurpose::PatientPhoto => ALLOWED_PATIENT_PHOTO_MIME_TYPES,
+            FilePurpose::VisitAttachment => ALLOWED_VISIT_ATTACHMENT_MIME_TYPES,
         };
 
         // Validate MIME type is in allowed list
@@ -292,6 +326,10 @@ impl FileUploadService {
             FilePurpose::Attachment,
             FilePurpose::Document,
             FilePurpose::Avatar,
+            FilePurpose::Branding,
+            FilePurpose::PatientPhoto,
+            FilePurpose::PatientAttachment,
+            FilePurpose::VisitAttachment,
         ] {
             let subdir = base_dir.join(purpose.subdirectory());
             fs::create_dir_all(&subdir)
@@ -477,6 +515,89 @@ impl FileUploadService {
         Ok(file)
     }
 
+    /// Upload a file encrypted at rest
+    ///
+    /// Validation, size and content hash apply to the plaintext; only the
+    /// stored bytes are encrypted. Read back with `get_decrypted_file_with_content`.
+    pub async fn upload_encrypted_file(
+        pool: &PgPool,
+        content: &[u8],
+        original_filename: &str,
+        purpose: FilePurpose,
+        description: Option<String>,
+        user_id: Uuid,
+        encryption_key: &EncryptionKey,
+    ) -> Result<UploadedFile> {
+        let validation = Self::validate_file(content, original_filename, purpose);
+        if !validation.is_valid {
+            return Err(anyhow!(
+                "File validation failed: {}",
+                validation.error_message.unwrap_or_default()
+            ));
+        }
+
+        let detected_mime = validation.detected_mime_type
+            .ok_or_else(|| anyhow!("Failed to detect MIME type"))?;
+
+        let sanitized_filename = Self::sanitize_filename(original_filename);
+        let stored_filename = Self::generate_stored_filename(&sanitized_filename);
+        let content_hash = Self::calculate_hash(content);
+
+        let encrypted = encryption_key
+            .encrypt_bytes(content)
+            .context("Failed to encrypt file content")?;
+        let storage_path = Self::save_file(&encrypted, &stored_filename, purpose).await?;
+
+        let file = sqlx::query_as::<_, UploadedFile>(
+            r#"
+            INSERT INTO uploaded_files (
+                id, original_filename, stored_filename, mime_type,
+                file_size_bytes, purpose, storage_path, content_hash,
+                description, created_by, is_encrypted
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, true)
+            RETURNING *
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(&sanitized_filename)
+        .bind(&stored_filename)
+        .bind(&detected_mime)
+        .bind(content.len() as i64)
+        .bind(purpose)
+        .bind(&storage_path)
+        .bind(&content_hash)
+        .bind(&description)
+        .bind(user_id)
+        .fetch_one(pool)
+        .await
+        .context("Failed to create file record")?;
+
+        Ok(file)
+    }
+
+    /// Get an encrypted file by ID with its decrypted content
+    pub async fn get_decrypted_file_with_content(
+        pool: &PgPool,
+        file_id: Uuid,
+        encryption_key: &EncryptionKey,
+    ) -> Result<Option<(UploadedFile, Vec<u8>)>> {
+        let Some(file) = Self::get_file(pool, file_id).await? else {
+            return Ok(None);
+        };
+
+        let stored = Self::read_file(&file.storage_path).await?;
+        let content = if file.is_encrypted {
+            encryption_key
+                .decrypt_bytes(&stored)
+                .context("Failed to decrypt file content")?
+        } else {
+            stored
+        };
+
+        Ok(Some((file, content)))
+    }
+
     /// Get file by ID
     pub async fn get_file(pool: &PgPool, file_id: Uuid) -> Result<Option<UploadedFile>> {
         let file = sqlx::query_as::<_, UploadedFile>(
@@ -551,7 +672,7 @@ impl FileUploadService {
         // For now, use a simpler approach without dynamic parameters
         // This is safe because we're not interpolating user input directly
         let total: (i64,) = sqlx::query_as(&format!(
-            "SELECT COUNT(*) FROM uploaded_files WHERE deleted_at IS NULL"
+            "SELECT COUNT(*) FROM uploaded_files WHERE deleted_at IS NULL AND NOT is_encrypted"
         ))
         .fetch_one(pool)
         .await
@@ -562,7 +683,7 @@ impl FileUploadService {
             sqlx::query_as::<_, UploadedFile>(
                 r#"
                 SELECT * FROM uploaded_files
-                WHERE deleted_at IS NULL AND purpose = $1
+                WHERE deleted_at IS NULL AND NOT is_encrypted AND purpose = $1
                 ORDER BY created_at DESC
                 LIMIT $2 OFFSET $3
                 "#,
@@ -577,7 +698,7 @@ impl FileUploadService {
             sqlx::query_as::<_, UploadedFile>(
                 r#"
                 SELECT * FROM uploaded_files
-                WHERE deleted_at IS NULL
+                WHERE deleted_at IS NULL AND NOT is_encrypted
                 ORDER BY created_at DESC
                 LIMIT $1 OFFSET $2
                 "#,
@@ -597,7 +718,7 @@ impl FileUploadService {
         })
     }
 
-    /// Update file metadata
+    /// Update file metadata (not of encrypted patient files)
     pub async fn update_file(
         pool: &PgPool,
         file_id: Uuid,
@@ -613,7 +734,7 @@ impl FileUploadService {
                 description = COALESCE($3, description),
                 updated_by = $4,
                 updated_at = NOW()
-            WHERE id = $1 AND deleted_at IS NULL
+            WHERE id = $1 AND deleted_at IS NULL AND NOT is_encrypted
             RETURNING *
             "#,
         )
@@ -630,10 +751,14 @@ impl FileUploadService {
     }
 
     /// Soft delete a file
+    ///
+    /// Encrypted patient files are managed through the patient endpoints and
+    /// are reported as not found.
     pub async fn delete_file_record(pool: &PgPool, file_id: Uuid) -> Result<()> {
         // Get file to find storage path
         let _file = Self::get_file(pool, file_id)
             .await?
+            .filter(|file| !file.is_encrypted)
             .ok_or_else(|| anyhow!("File not found"))?;
 
         // Soft delete in database
@@ -814,6 +939,38 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_detect_mime_type_audio() {
+        let cases: [(&[u8], &str); 5] = [
+            (b"ID3\x04\x00\x00\x00\x00", "audio/mpeg"),
+            (b"RIFF\x24\x08\x00\x00WAVEfmt ", "audio/wav"),
+            (b"OggS\x00\x02\x00\x00", "audio/ogg"),
+            (&[0x1A, 0x45, 0xDF, 0xA3, 0x9F, 0x42, 0x86, 0x81], "audio/webm"),
+            (b"\x00\x00\x00\x20ftypM4A ", "audio/mp4"),
+        ];
+        for (bytes, expected) in cases {
+            assert_eq!(
+                FileUploadService::detect_mime_type(bytes),
+                Some(expected.to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn test_validate_file_visit_attachment() {
+        let memo = b"OggS\x00\x02\x00\x00\x00\x00";
+        let result = FileUploadService::validate_file(memo, "memo.ogg", FilePurpose::VisitAttachment);
+        assert!(result.is_valid);
+
+        // Audio is only accepted on visits, GIFs are not clinical photos
+        let result =
+            FileUploadService::validate_file(memo, "memo.ogg", FilePurpose::PatientAttachment);
+        assert!(!result.is_valid);
+        let result =
+            FileUploadService::validate_file(b"GIF89a", "a.gif", FilePurpose::VisitAttachment);
+        assert!(!result.is_valid);
+    }
+
     #[test]
     fn test_detect_mime_type_unknown() {
         let unknown_bytes = vec![0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07];
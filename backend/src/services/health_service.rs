@@ -15,7 +15,7 @@ use crate::models::{
     ApplicationInfo, BackupInfo, BackupStatusFile, BackupStatusResponse, ComponentHealth,
     DatabaseInfo, DatabasePoolMetrics, DatabaseStorageStats, DetailedHealthResponse,
     EnvironmentInfo, FileSystemStats, HealthStatus, ServerInfo, StorageBreakdown,
-    StorageStatsResponse, SystemInfoResponse, SystemResources, TableStorageInfo,
+    StorageStatsResponse, SystemAlert, SystemInfoResponse, SystemResources, TableStorageInfo,
 };
 use chrono::{DateTime, Utc};
 use sqlx::PgPool;
@@ -32,6 +32,7 @@ impl SystemHealthService {
     /// - Database connectivity and latency
     /// - Database pool status
     /// - System resources (memory, CPU, disk)
+    /// - Open alerts raised by background jobs
     ///
     /// # Arguments
     /// * `pool` - Database connection pool
@@ -113,6 +114,13 @@ impl SystemHealthService {
             }
         }
 
+        // Check open alerts raised by background tasks (e.g. stuck-state janitor)
+        let alerts_health = Self::check_background_jobs_health(pool).await;
+        if alerts_health.status == HealthStatus::Degraded && overall_status == HealthStatus::Healthy {
+            overall_status = HealthStatus::Degraded;
+        }
+        components.push(alerts_health);
+
         let uptime = start_time.elapsed().unwrap_or_default().as_secs();
 
         DetailedHealthResponse {
@@ -153,6 +161,47 @@ impl SystemHealthService {
         }
     }
 
+    /// Check open alerts raised by background jobs
+    ///
+    /// Any unacknowledged WARNING or CRITICAL alert marks the component as
+    /// degraded until an admin acknowledges it.
+    async fn check_background_jobs_health(pool: &PgPool) -> ComponentHealth {
+        match SystemAlert::count_open(pool).await {
+            Ok(counts) => {
+                let count_of = |severity: &str| {
+                    counts
+                        .iter()
+                        .find(|(s, _)| s == severity)
+                        .map(|(_, n)| *n)
+                        .unwrap_or(0)
+                };
+                let warning = count_of("WARNING");
+                let critical = count_of("CRITICAL");
+                let details = serde_json::json!({
+                    "open_alerts": {
+                        "info": count_of("INFO"),
+                        "warning": warning,
+                        "critical": critical,
+                    }
+                });
+
+                if warning + critical > 0 {
+                    ComponentHealth::degraded(
+                        "background_jobs",
+                        &format!("{} unacknowledged alert(s)", warning + critical),
+                    )
+                    .with_details(details)
+                } else {
+                    ComponentHealth::healthy("background_jobs").with_details(details)
+                }
+            }
+            Err(e) => ComponentHealth::degraded(
+                "background_jobs",
+                &format!("Failed to read system alerts: {}", e),
+            ),
+        }
+    }
+
     /// Get database connection pool metrics
     fn get_pool_metrics(pool: &PgPool) -> DatabasePoolMetrics {
         let size = pool.size();
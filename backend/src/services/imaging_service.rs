@@ -0,0 +1,443 @@
+/*!
+ * Imaging Service
+ *
+ * Imaging orders under RLS (doctors order and edit, nurses read). The
+ * clinical question and the report are encrypted.
+ *
+ * Orders are medical records: deleting one only marks it deleted.
+ *
+ * Status moves forward only (ORDERED -> PERFORMED -> REPORTED, or CANCELLED
+ * before the report); the referral letter itself is rendered by
+ * `DocumentService::generate_imaging_referral`.
+ */
+
+use crate::{
+    db::rls::begin_with_rls,
+    models::{
+        CreateImagingOrderRequest, ImagingOrder, ImagingOrderResponse, ImagingOrderStatus,
+        ListImagingOrdersQuery, UpdateImagingOrderRequest,
+    },
+    utils::{encryption::EncryptionKey, AppError, Result},
+};
+use chrono::{NaiveDate, Utc};
+use sqlx::{PgConnection, PgPool};
+use tracing::info;
+use uuid::Uuid;
+
+const ORDER_COLUMNS: &str = r#"
+    id, patient_id, visit_id, visit_date, modality, body_part, laterality, contrast,
+    clinical_question, urgency, status, facility, performed_at, reported_at, report,
+    referral_document_id, ordered_at, created_by, created_at, updated_by, updated_at
+"#;
+
+/// Imaging service
+pub struct ImagingService {
+    pool: PgPool,
+    encryption_key: EncryptionKey,
+}
+
+impl ImagingService {
+    /// Create a new imaging service
+    pub fn new(pool: PgPool, encryption_key: EncryptionKey) -> Self {
+        Self {
+            pool,
+            encryption_key,
+        }
+    }
+
+    /// Place an imaging order
+    ///
+    /// Fails with Validation for a visit that is not the patient's, and with
+    /// Conflict if the patient is anonymized or merged.
+    pub async fn create_order(
+        &self,
+        patient_id: Uuid,
+        req: &CreateImagingOrderRequest,
+        user_id: Uuid,
+    ) -> Result<ImagingOrderResponse> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        self.check_patient(&mut tx, patient_id).await?;
+
+        let visit_date = match req.visit_id {
+            Some(visit_id) => Some(self.visit_date(&mut tx, patient_id, visit_id).await?),
+            None => None,
+        };
+
+        let order = sqlx::query_as::<_, ImagingOrder>(&format!(
+            r#"
+            INSERT INTO imaging_orders (
+                patient_id, visit_id, visit_date, modality, body_part, laterality, contrast,
+                clinical_question, urgency, facility, created_by
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            RETURNING {}
+            "#,
+            ORDER_COLUMNS
+        ))
+        .bind(patient_id)
+        .bind(req.visit_id)
+        .bind(visit_date)
+        .bind(req.modality.as_str())
+        .bind(req.body_part.trim())
+        .bind(req.laterality.map(|l| l.as_str()))
+        .bind(req.contrast)
+        .bind(self.encrypt(req.clinical_question.trim())?)
+        .bind(req.urgency.as_str())
+        .bind(req.facility.as_deref().map(str::trim))
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        info!(
+            "Imaging order {} ({}) placed for patient {} by {}",
+            order.id, order.modality, patient_id, user_id
+        );
+
+        self.decrypt_order(&order)
+    }
+
+    /// List a patient's imaging orders, newest first
+    pub async fn list_orders(
+        &self,
+        patient_id: Uuid,
+        query: &ListImagingOrdersQuery,
+        user_id: Uuid,
+    ) -> Result<Vec<ImagingOrderResponse>> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        let orders = sqlx::query_as::<_, ImagingOrder>(&format!(
+            r#"
+            SELECT {}
+            FROM imaging_orders
+            WHERE deleted_at IS NULL
+              AND patient_id = $1
+              AND ($2::TEXT IS NULL OR status = $2)
+              AND ($3::TEXT IS NULL OR modality = $3)
+              AND ($4::UUID IS NULL OR visit_id = $4)
+            ORDER BY ordered_at DESC
+            "#,
+            ORDER_COLUMNS
+        ))
+        .bind(patient_id)
+        .bind(query.status.map(|s| s.as_str()))
+        .bind(query.modality.map(|m| m.as_str()))
+        .bind(query.visit_id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        orders.iter().map(|o| self.decrypt_order(o)).collect()
+    }
+
+    /// Get an imaging order
+    pub async fn get_order(
+        &self,
+        patient_id: Uuid,
+        order_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<ImagingOrderResponse> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        let order = self.find_order(&mut tx, patient_id, order_id).await?;
+        tx.commit().await?;
+
+        self.decrypt_order(&order)
+    }
+
+    /// Update an imaging order
+    ///
+    /// Fails with Conflict for a status change the order cannot make (see
+    /// `ImagingOrderStatus::can_transition_to`) and for any change to a
+    /// cancelled order. A report can only be added to a reported order, or
+    /// with the change to REPORTED.
+    pub async fn update_order(
+        &self,
+        patient_id: Uuid,
+        order_id: Uuid,
+        req: &UpdateImagingOrderRequest,
+        user_id: Uuid,
+    ) -> Result<ImagingOrderResponse> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        let existing = self.find_order(&mut tx, patient_id, order_id).await?;
+
+        let current = ImagingOrderStatus::from_db(&existing.status).ok_or_else(|| {
+            AppError::Internal(format!("Unknown imaging order status {}", existing.status))
+        })?;
+        if current == ImagingOrderStatus::Cancelled {
+            return Err(AppError::Conflict(format!(
+                "Imaging order {} has been cancelled",
+                order_id
+            )));
+        }
+
+        let status = match req.status {
+            Some(next) if next != current => {
+                if !current.can_transition_to(next) {
+                    return Err(AppError::Conflict(format!(
+                        "Imaging order {} cannot move from {} to {}",
+                        order_id,
+                        current.as_str(),
+                        next.as_str()
+                    )));
+                }
+                next
+            }
+            _ => current,
+        };
+
+        if req.report.is_some() && status != ImagingOrderStatus::Reported {
+            return Err(AppError::Validation(
+                "A report can only be recorded on a REPORTED order".to_string(),
+            ));
+        }
+
+        // Reporting an exam that was never marked performed: it was
+        // performed by the time it was reported
+        let now = Utc::now();
+        let performed_at = match status {
+            ImagingOrderStatus::Performed | ImagingOrderStatus::Reported => {
+                req.performed_at.or(existing.performed_at).or(Some(now))
+            }
+            _ => existing.performed_at,
+        };
+        let reported_at = match status {
+            ImagingOrderStatus::Reported => req.reported_at.or(existing.reported_at).or(Some(now)),
+            _ => existing.reported_at,
+        };
+        if let (Some(performed), Some(reported)) = (performed_at, reported_at) {
+            if reported < performed {
+                return Err(AppError::Validation(
+                    "reported_at cannot be before performed_at".to_string(),
+                ));
+            }
+        }
+
+        sqlx::query(
+            r#"
+            UPDATE imaging_orders
+            SET status = $3,
+                body_part = COALESCE($4, body_part),
+                laterality = COALESCE($5, laterality),
+                contrast = COALESCE($6, contrast),
+                clinical_question = COALESCE($7, clinical_question),
+                urgency = COALESCE($8, urgency),
+                facility = COALESCE($9, facility),
+                performed_at = $10,
+                reported_at = $11,
+                report = COALESCE($12, report),
+                updated_by = $13
+            WHERE id = $1 AND patient_id = $2 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(order_id)
+        .bind(patient_id)
+        .bind(status.as_str())
+        .bind(req.body_part.as_deref().map(str::trim))
+        .bind(req.laterality.map(|l| l.as_str()))
+        .bind(req.contrast)
+        .bind(
+            req.clinical_question
+                .as_deref()
+                .map(|q| self.encrypt(q.trim()))
+                .transpose()?,
+        )
+        .bind(req.urgency.map(|u| u.as_str()))
+        .bind(req.facility.as_deref().map(str::trim))
+        .bind(performed_at)
+        .bind(reported_at)
+        .bind(req.report.as_deref().map(|r| self.encrypt(r)).transpose()?)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+        let order = self.find_order(&mut tx, patient_id, order_id).await?;
+        tx.commit().await?;
+
+        if status != current {
+            info!(
+                "Imaging order {} moved from {} to {} by {}",
+                order_id,
+                current.as_str(),
+                status.as_str(),
+                user_id
+            );
+        }
+
+        self.decrypt_order(&order)
+    }
+
+    /// Mark an imaging order deleted
+    pub async fn delete_order(
+        &self,
+        patient_id: Uuid,
+        order_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<()> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        let deleted = sqlx::query(
+            r#"
+            UPDATE imaging_orders
+            SET deleted_at = NOW(), deleted_by = $3, updated_by = $3
+            WHERE id = $1 AND patient_id = $2 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(order_id)
+        .bind(patient_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        if deleted == 0 {
+            return Err(AppError::NotFound(format!(
+                "Imaging order {} not found",
+                order_id
+            )));
+        }
+
+        tx.commit().await?;
+
+        info!(
+            "Imaging order {} of patient {} deleted by {}",
+            order_id, patient_id, user_id
+        );
+
+        Ok(())
+    }
+
+    /// Get an order ready to be printed as a referral letter
+    ///
+    /// Fails with Conflict if the order has been cancelled.
+    pub async fn prepare_referral(
+        &self,
+        patient_id: Uuid,
+        order_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<ImagingOrderResponse> {
+        let order = self.get_order(patient_id, order_id, user_id).await?;
+
+        if order.status == ImagingOrderStatus::Cancelled.as_str() {
+            return Err(AppError::Conflict(format!(
+                "Imaging order {} has been cancelled",
+                order_id
+            )));
+        }
+
+        Ok(order)
+    }
+
+    /// Link the referral letter generated for an order
+    pub async fn link_referral(
+        &self,
+        patient_id: Uuid,
+        order_id: Uuid,
+        document_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<ImagingOrderResponse> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        sqlx::query(
+            r#"
+            UPDATE imaging_orders
+            SET referral_document_id = $3, updated_by = $4
+            WHERE id = $1 AND patient_id = $2 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(order_id)
+        .bind(patient_id)
+        .bind(document_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+        let order = self.find_order(&mut tx, patient_id, order_id).await?;
+        tx.commit().await?;
+
+        info!(
+            "Referral letter {} generated for imaging order {} by {}",
+            document_id, order_id, user_id
+        );
+
+        self.decrypt_order(&order)
+    }
+
+    async fn find_order(
+        &self,
+        tx: &mut PgConnection,
+        patient_id: Uuid,
+        order_id: Uuid,
+    ) -> Result<ImagingOrder> {
+        sqlx::query_as::<_, ImagingOrder>(&format!(
+            r#"
+            SELECT {}
+            FROM imaging_orders
+            WHERE id = $1 AND patient_id = $2 AND deleted_at IS NULL
+            "#,
+            ORDER_COLUMNS
+        ))
+        .bind(order_id)
+        .bind(patient_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Imaging order {} not found", order_id)))
+    }
+
+    /// The patient must exist and be neither anonymized nor merged
+    async fn check_patient(&self, tx: &mut PgConnection, patient_id: Uuid) -> Result<()> {
+        let patient: Option<(
+            Option<chrono::DateTime<chrono::Utc>>,
+            Option<chrono::DateTime<chrono::Utc>>,
+        )> = sqlx::query_as("SELECT anonymized_at, merged_at FROM patients WHERE id = $1")
+            .bind(patient_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        match patient {
+            None => Err(AppError::NotFound(format!(
+                "Patient {} not found",
+                patient_id
+            ))),
+            Some((Some(_), _)) => Err(AppError::Conflict(format!(
+                "Patient {} has been anonymized",
+                patient_id
+            ))),
+            Some((_, Some(_))) => Err(AppError::Conflict(format!(
+                "Patient {} has been merged into another record",
+                patient_id
+            ))),
+            Some(_) => Ok(()),
+        }
+    }
+
+    /// Date of a visit of the patient (the visits partition key)
+    async fn visit_date(
+        &self,
+        tx: &mut PgConnection,
+        patient_id: Uuid,
+        visit_id: Uuid,
+    ) -> Result<NaiveDate> {
+        sqlx::query_scalar("SELECT visit_date FROM visits WHERE id = $1 AND patient_id = $2")
+            .bind(visit_id)
+            .bind(patient_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| {
+                AppError::Validation(format!("Visit {} is not a visit of this patient", visit_id))
+            })
+    }
+
+    fn decrypt_order(&self, order: &ImagingOrder) -> Result<ImagingOrderResponse> {
+        order
+            .decrypt(&self.encryption_key)
+            .map_err(|e| AppError::Internal(format!("Failed to decrypt imaging order: {}", e)))
+    }
+
+    fn encrypt(&self, value: &str) -> Result<String> {
+        self.encryption_key
+            .encrypt(value)
+            .map_err(|e| AppError::Internal(format!("Failed to encrypt imaging data: {}", e)))
+    }
+}
@@ -0,0 +1,112 @@
+/*!
+ * Impersonation Service
+ *
+ * Records admin impersonation sessions (see `models::impersonation`). Token
+ * issuing lives in JwtService; ending a session also requires adding its
+ * token to the denylist, which the handlers do.
+ */
+
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::impersonation::ImpersonationSession;
+use crate::utils::Result;
+
+/// Columns selected for ImpersonationSession
+const SESSION_COLUMNS: &str = r#"
+    id, impersonator_id, target_user_id, reason, token_jti,
+    started_at, expires_at, ended_at, host(ip_address) AS ip_address
+"#;
+
+/// Impersonation session service
+pub struct ImpersonationService {
+    pool: PgPool,
+}
+
+impl ImpersonationService {
+    /// Create a new impersonation service
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Record a started session
+    pub async fn create(
+        &self,
+        impersonator_id: Uuid,
+        target_user_id: Uuid,
+        reason: &str,
+        token_jti: &str,
+        expires_at: DateTime<Utc>,
+        ip_address: Option<&str>,
+    ) -> Result<ImpersonationSession> {
+        let ip_address = ip_address.filter(|ip| ip.parse::<std::net::IpAddr>().is_ok());
+
+        let session = sqlx::query_as::<_, ImpersonationSession>(&format!(
+            r#"
+            INSERT INTO impersonation_sessions
+                (impersonator_id, target_user_id, reason, token_jti, expires_at, ip_address)
+            VALUES ($1, $2, $3, $4, $5, $6::inet)
+            RETURNING {}
+            "#,
+            SESSION_COLUMNS
+        ))
+        .bind(impersonator_id)
+        .bind(target_user_id)
+        .bind(reason)
+        .bind(token_jti)
+        .bind(expires_at)
+        .bind(ip_address)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(session)
+    }
+
+    /// End the session issued with `token_jti`
+    ///
+    /// Returns None if there is no open session for the token.
+    pub async fn end(&self, token_jti: &str) -> Result<Option<ImpersonationSession>> {
+        let session = sqlx::query_as::<_, ImpersonationSession>(&format!(
+            r#"
+            UPDATE impersonation_sessions
+            SET ended_at = NOW()
+            WHERE token_jti = $1 AND ended_at IS NULL
+            RETURNING {}
+            "#,
+            SESSION_COLUMNS
+        ))
+        .bind(token_jti)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(session)
+    }
+
+    /// Recent sessions, newest first, optionally for one admin or target user
+    pub async fn list(
+        &self,
+        impersonator_id: Option<Uuid>,
+        target_user_id: Option<Uuid>,
+        limit: i64,
+    ) -> Result<Vec<ImpersonationSession>> {
+        let sessions = sqlx::query_as::<_, ImpersonationSession>(&format!(
+            r#"
+            SELECT {}
+            FROM impersonation_sessions
+            WHERE ($1::uuid IS NULL OR impersonator_id = $1)
+              AND ($2::uuid IS NULL OR target_user_id = $2)
+            ORDER BY started_at DESC
+            LIMIT $3
+            "#,
+            SESSION_COLUMNS
+        ))
+        .bind(impersonator_id)
+        .bind(target_user_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(sessions)
+    }
+}
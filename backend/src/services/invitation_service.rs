@@ -0,0 +1,464 @@
+/*!
+ * User Invitation Service
+ *
+ * Business logic for inviting staff by email:
+ * - Creating, re-sending and revoking invitations (admin)
+ * - Resolving an invitation from its token (invitee)
+ * - Accepting an invitation: creating the user with password and MFA already
+ *   configured, in a single transaction
+ *
+ * Tokens are only ever held in memory and in the invitation email; the
+ * database stores their SHA-256 hash.
+ */
+
+use chrono::{Duration, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::user_invitation::{
+    generate_invitation_token, hash_invitation_token, CreateInvitationRequest, InvitationStatus,
+    UserInvitation, DEFAULT_INVITATION_EXPIRY_HOURS, INVITATION_EXPIRY_SETTING_KEY,
+};
+use crate::models::User;
+use crate::services::email_service::EmailService;
+use crate::services::{BrandingService, SettingsService};
+use crate::utils::html::escape_html;
+use crate::utils::{AppError, Result};
+
+/// Columns selected for UserInvitation
+const INVITATION_COLUMNS: &str = "id, email, role, first_name, last_name, phone, token_hash, \
+    expires_at, accepted_at, accepted_user_id, revoked_at, invited_by, created_at, updated_at";
+
+/// Frontend route that handles invitation links
+const ACCEPT_INVITATION_PATH: &str = "/accept-invitation";
+
+/// Credentials chosen by the invitee, already validated and hashed
+pub struct InvitationCredentials {
+    pub username: String,
+    pub password_hash: String,
+    pub mfa_secret: String,
+    pub hashed_backup_codes: Vec<String>,
+}
+
+/// Service for the user invitation flow
+pub struct InvitationService {
+    pool: PgPool,
+}
+
+impl InvitationService {
+    /// Create a new invitation service
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    // =========================================================================
+    // Admin Operations
+    // =========================================================================
+
+    /// Create an invitation and return it with its (unhashed) token
+    ///
+    /// Fails with Conflict if a user with that email already exists or an
+    /// open invitation is outstanding.
+    pub async fn create_invitation(
+        &self,
+        settings_service: &SettingsService,
+        request: &CreateInvitationRequest,
+        invited_by: Uuid,
+    ) -> Result<(UserInvitation, String)> {
+        let email = request.email.trim().to_lowercase();
+
+        let email_taken: bool =
+            sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM users WHERE LOWER(email) = $1)")
+                .bind(&email)
+                .fetch_one(&self.pool)
+                .await?;
+
+        if email_taken {
+            return Err(AppError::Conflict(
+                "A user with this email already exists".to_string(),
+            ));
+        }
+
+        let token = generate_invitation_token();
+        let expires_at = Utc::now() + Duration::hours(self.expiry_hours(settings_service).await);
+
+        let invitation = sqlx::query_as::<_, UserInvitation>(&format!(
+            r#"
+            INSERT INTO user_invitations (
+                email, role, first_name, last_name, phone, token_hash, expires_at, invited_by
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING {}
+            "#,
+            INVITATION_COLUMNS
+        ))
+        .bind(&email)
+        .bind(&request.role)
+        .bind(request.first_name.trim())
+        .bind(request.last_name.trim())
+        .bind(&request.phone)
+        .bind(hash_invitation_token(&token))
+        .bind(expires_at)
+        .bind(invited_by)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(ref db_err) if db_err.constraint().is_some() => {
+                AppError::Conflict(
+                    "An open invitation for this email already exists".to_string(),
+                )
+            }
+            _ => AppError::from(e),
+        })?;
+
+        Ok((invitation, token))
+    }
+
+    /// List invitations, newest first, optionally filtered by derived status
+    pub async fn list_invitations(
+        &self,
+        status: Option<InvitationStatus>,
+    ) -> Result<Vec<UserInvitation>> {
+        let invitations = sqlx::query_as::<_, UserInvitation>(&format!(
+            "SELECT {} FROM user_invitations ORDER BY created_at DESC LIMIT 500",
+            INVITATION_COLUMNS
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(match status {
+            Some(status) => invitations
+                .into_iter()
+                .filter(|i| i.status() == status)
+                .collect(),
+            None => invitations,
+        })
+    }
+
+    /// Get an invitation by ID
+    pub async fn get_invitation(&self, id: Uuid) -> Result<UserInvitation> {
+        sqlx::query_as::<_, UserInvitation>(&format!(
+            "SELECT {} FROM user_invitations WHERE id = $1",
+            INVITATION_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Invitation not found".to_string()))
+    }
+
+    /// Revoke an open invitation so its link stops working
+    pub async fn revoke_invitation(&self, id: Uuid) -> Result<UserInvitation> {
+        let invitation = self.get_invitation(id).await?;
+
+        if invitation.accepted_at.is_some() {
+            return Err(AppError::Conflict(
+                "Invitation has already been accepted".to_string(),
+            ));
+        }
+        if invitation.revoked_at.is_some() {
+            return Ok(invitation);
+        }
+
+        let invitation = sqlx::query_as::<_, UserInvitation>(&format!(
+            r#"
+            UPDATE user_invitations
+            SET revoked_at = NOW()
+            WHERE id = $1 AND accepted_at IS NULL AND revoked_at IS NULL
+            RETURNING {}
+            "#,
+            INVITATION_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| AppError::Conflict("Invitation is no longer open".to_string()))?;
+
+        Ok(invitation)
+    }
+
+    /// Issue a new token (and validity window) for an unaccepted invitation
+    ///
+    /// The previous link stops working. Revoked invitations cannot be re-sent;
+    /// create a new one instead.
+    pub async fn renew_invitation(
+        &self,
+        settings_service: &SettingsService,
+        id: Uuid,
+    ) -> Result<(UserInvitation, String)> {
+        let token = generate_invitation_token();
+        let expires_at = Utc::now() + Duration::hours(self.expiry_hours(settings_service).await);
+
+        let invitation = sqlx::query_as::<_, UserInvitation>(&format!(
+            r#"
+            UPDATE user_invitations
+            SET token_hash = $2, expires_at = $3
+            WHERE id = $1 AND accepted_at IS NULL AND revoked_at IS NULL
+            RETURNING {}
+            "#,
+            INVITATION_COLUMNS
+        ))
+        .bind(id)
+        .bind(hash_invitation_token(&token))
+        .bind(expires_at)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        match invitation {
+            Some(invitation) => Ok((invitation, token)),
+            None => {
+                // Distinguish missing from closed invitations
+                self.get_invitation(id).await?;
+                Err(AppError::Conflict(
+                    "Only open invitations can be re-sent".to_string(),
+                ))
+            }
+        }
+    }
+
+    /// Email the invitation link to the invitee
+    ///
+    /// Returns the link and whether the email was sent. The link is absolute
+    /// when `branding.public_base_url` is configured, relative otherwise.
+    pub async fn send_invitation_email(
+        &self,
+        email_service: Option<&EmailService>,
+        invitation: &UserInvitation,
+        token: &str,
+    ) -> (String, bool) {
+        let base_url = BrandingService::new(self.pool.clone())
+            .load_settings()
+            .await
+            .ok()
+            .and_then(|s| s.public_base_url)
+            .unwrap_or_default();
+        let url = invitation_url(&base_url, token);
+
+        let Some(email_service) = email_service.filter(|e| e.is_enabled()) else {
+            return (url, false);
+        };
+
+        let name = format!("{} {}", invitation.first_name, invitation.last_name);
+        let (subject, text, html) = invitation_email(invitation, &url);
+
+        match email_service
+            .send_notification(&invitation.email, &name, &subject, &text, Some(&html))
+            .await
+        {
+            Ok(result) if result.success => (url, true),
+            Ok(result) => {
+                tracing::warn!("Invitation email {} not sent: {}", invitation.id, result.message);
+                (url, false)
+            }
+            Err(e) => {
+                tracing::error!("Failed to send invitation email {}: {}", invitation.id, e);
+                (url, false)
+            }
+        }
+    }
+
+    // =========================================================================
+    // Invitee Operations
+    // =========================================================================
+
+    /// Resolve an open invitation from its token
+    ///
+    /// Unknown, revoked, accepted and expired tokens all yield the same
+    /// NotFound error so the endpoint cannot be used to probe invitations.
+    pub async fn find_open_by_token(&self, token: &str) -> Result<UserInvitation> {
+        let invitation = sqlx::query_as::<_, UserInvitation>(&format!(
+            "SELECT {} FROM user_invitations WHERE token_hash = $1",
+            INVITATION_COLUMNS
+        ))
+        .bind(hash_invitation_token(token))
+        .fetch_optional(&self.pool)
+        .await?;
+
+        match invitation {
+            Some(invitation) if invitation.status() == InvitationStatus::Pending => Ok(invitation),
+            _ => Err(AppError::NotFound(
+                "Invitation is invalid or has expired".to_string(),
+            )),
+        }
+    }
+
+    /// Accept an invitation and create the user account
+    ///
+    /// The invitation row is locked for the duration of the transaction so the
+    /// same link cannot create two accounts.
+    pub async fn accept_invitation(
+        &self,
+        token: &str,
+        credentials: InvitationCredentials,
+    ) -> Result<(UserInvitation, User)> {
+        let mut tx = self.pool.begin().await?;
+
+        let invitation = sqlx::query_as::<_, UserInvitation>(&format!(
+            "SELECT {} FROM user_invitations WHERE token_hash = $1 FOR UPDATE",
+            INVITATION_COLUMNS
+        ))
+        .bind(hash_invitation_token(token))
+        .fetch_optional(&mut *tx)
+        .await?
+        .filter(|i| i.status() == InvitationStatus::Pending)
+        .ok_or_else(|| AppError::NotFound("Invitation is invalid or has expired".to_string()))?;
+
+        let user = sqlx::query_as::<_, User>(
+            r#"
+            INSERT INTO users (
+                username, email, password_hash, role, first_name, last_name, phone,
+                is_active, mfa_secret, mfa_enabled, backup_codes, created_by
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, true, $8, true, $9, $10)
+            RETURNING *
+            "#,
+        )
+        .bind(&credentials.username)
+        .bind(&invitation.email)
+        .bind(&credentials.password_hash)
+        .bind(&invitation.role)
+        .bind(&invitation.first_name)
+        .bind(&invitation.last_name)
+        .bind(&invitation.phone)
+        .bind(&credentials.mfa_secret)
+        .bind(&credentials.hashed_backup_codes)
+        .bind(invitation.invited_by)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(ref db_err) if db_err.constraint().is_some() => {
+                AppError::Conflict("Username or email is already in use".to_string())
+            }
+            _ => AppError::from(e),
+        })?;
+
+        let invitation = sqlx::query_as::<_, UserInvitation>(&format!(
+            r#"
+            UPDATE user_invitations
+            SET accepted_at = NOW(), accepted_user_id = $2
+            WHERE id = $1
+            RETURNING {}
+            "#,
+            INVITATION_COLUMNS
+        ))
+        .bind(invitation.id)
+        .bind(user.id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok((invitation, user))
+    }
+
+    // =========================================================================
+    // Helpers
+    // =========================================================================
+
+    /// Invitation validity window in hours (from settings)
+    async fn expiry_hours(&self, settings_service: &SettingsService) -> i64 {
+        settings_service
+            .get_setting(INVITATION_EXPIRY_SETTING_KEY)
+            .await
+            .ok()
+            .flatten()
+            .and_then(|s| s.setting_value.as_i64())
+            .filter(|hours| *hours > 0)
+            .unwrap_or(DEFAULT_INVITATION_EXPIRY_HOURS)
+    }
+}
+
+/// Build the invitation link for a token
+fn invitation_url(base_url: &str, token: &str) -> String {
+    format!(
+        "{}{}?token={}",
+        base_url.trim().trim_end_matches('/'),
+        ACCEPT_INVITATION_PATH,
+        token
+    )
+}
+
+/// Build the invitation email (subject, plain text, HTML)
+fn invitation_email(invitation: &UserInvitation, url: &str) -> (String, String, String) {
+    let subject = "You have been invited to DocPat".to_string();
+    let expires = invitation.expires_at.format("%d/%m/%Y %H:%M UTC");
+
+    let text = format!(
+        "Dear {first_name} {last_name},\n\n\
+         You have been invited to join DocPat as {role}.\n\n\
+         To activate your account, open the link below, choose a username and password \
+         and set up two-factor authentication:\n\n\
+         {url}\n\n\
+         This link expires on {expires}. If you were not expecting this invitation, \
+         you can ignore this email.\n\n\
+         Best regards,\nDocPat Medical Practice",
+        first_name = invitation.first_name,
+        last_name = invitation.last_name,
+        role = invitation.role,
+    );
+
+    let html = format!(
+        r#"<!DOCTYPE html>
+<html>
+<head><meta charset="utf-8"></head>
+<body style="font-family: Arial, sans-serif; line-height: 1.6; color: #333;">
+<div style="max-width: 600px; margin: 0 auto; padding: 20px;">
+<p>Dear {first_name} {last_name},</p>
+<p>You have been invited to join DocPat as <strong>{role}</strong>.</p>
+<p>To activate your account, choose a username and password and set up two-factor authentication:</p>
+<p><a href="{url}">Accept invitation</a></p>
+<p style="font-size: 12px; color: #666;">This link expires on {expires}. If you were not expecting this invitation, you can ignore this email.</p>
+</div>
+</body>
+</html>"#,
+        first_name = escape_html(&invitation.first_name),
+        last_name = escape_html(&invitation.last_name),
+        role = invitation.role,
+        url = escape_html(url),
+    );
+
+    (subject, text, html)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::UserRole;
+
+    #[test]
+    fn test_invitation_url() {
+        assert_eq!(
+            invitation_url("https://studio.example.com/", "abc"),
+            "https://studio.example.com/accept-invitation?token=abc"
+        );
+        assert_eq!(invitation_url("", "abc"), "/accept-invitation?token=abc");
+    }
+
+    #[test]
+    fn test_invitation_email_escapes_names() {
+        let now = Utc::now();
+        let invitation = UserInvitation {
+            id: Uuid::new_v4(),
+            email: "x@example.com".to_string(),
+            role: UserRole::Doctor,
+            first_name: "<b>Anna</b>".to_string(),
+            last_name: "Rossi".to_string(),
+            phone: None,
+            token_hash: String::new(),
+            expires_at: now,
+            accepted_at: None,
+            accepted_user_id: None,
+            revoked_at: None,
+            invited_by: Uuid::new_v4(),
+            created_at: now,
+            updated_at: now,
+        };
+
+        let (subject, text, html) = invitation_email(&invitation, "/accept-invitation?token=abc");
+        assert!(subject.contains("DocPat"));
+        assert!(text.contains("/accept-invitation?token=abc"));
+        assert!(text.contains("DOCTOR"));
+        assert!(html.contains("&lt;b&gt;Anna&lt;/b&gt;"));
+        assert!(!html.contains("<b>Anna</b>"));
+    }
+}
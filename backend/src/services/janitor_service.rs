@@ -0,0 +1,465 @@
+/*!
+ * Stuck-State Janitor
+ *
+ * Background task that finds rows stuck in transient states and fails them
+ * with a diagnostic error so they stop lingering unnoticed:
+ * - Notifications in PROCESSING whose worker lease expired (or was never
+ *   taken) longer than a configurable threshold ago
+ * - Documents in GENERATING longer than a configurable threshold
+ * - Documents marked GENERATED whose PDF is missing from storage
+ *
+ * Every recovery is logged and recorded as an internal alert in
+ * `system_alerts`, which admins see via `GET /api/v1/system/alerts` and the
+ * detailed health check.
+ *
+ * Failed notifications go through the normal retry schedule afterwards.
+ */
+
+use crate::db::rls::{apply_rls_context, SYSTEM_ROLE, SYSTEM_USER_ID};
+use crate::models::{AlertSeverity, CreateSystemAlert, SystemAlert};
+use crate::services::SettingsService;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use std::sync::Arc;
+use tokio::time::{sleep, Duration as TokioDuration};
+use tracing::{error, info, info_span, warn, Instrument};
+use uuid::Uuid;
+
+/// Source recorded on alerts raised by the janitor
+const ALERT_SOURCE: &str = "janitor";
+
+/// Error code stored on notifications failed by the janitor
+pub const STUCK_NOTIFICATION_ERROR_CODE: &str = "STUCK_PROCESSING";
+
+/// Maximum GENERATED documents checked for a missing file per run
+const DOCUMENT_FILE_SCAN_LIMIT: i64 = 500;
+
+/// Janitor configuration loaded from settings
+#[derive(Debug, Clone)]
+pub struct JanitorConfig {
+    /// Whether the janitor is enabled
+    pub enabled: bool,
+    /// Minutes between runs
+    pub interval_minutes: i64,
+    /// Minutes a notification may stay in PROCESSING without a live lease
+    pub notification_stuck_minutes: i64,
+    /// Minutes a document may stay in GENERATING
+    pub document_stuck_minutes: i64,
+}
+
+impl Default for JanitorConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_minutes: 10,
+            notification_stuck_minutes: 30,
+            document_stuck_minutes: 15,
+        }
+    }
+}
+
+/// Outcome of a single janitor run
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct JanitorReport {
+    pub notifications_failed: usize,
+    pub documents_failed: usize,
+    pub missing_files_failed: usize,
+}
+
+impl JanitorReport {
+    pub fn total(&self) -> usize {
+        self.notifications_failed + self.documents_failed + self.missing_files_failed
+    }
+}
+
+/// Notification failed by the janitor
+#[derive(Debug, sqlx::FromRow)]
+struct StuckNotification {
+    id: Uuid,
+    claimed_by: Option<String>,
+    stuck_since: DateTime<Utc>,
+}
+
+/// Document candidate for recovery
+#[derive(Debug, sqlx::FromRow)]
+struct DocumentCandidate {
+    id: Uuid,
+    provider_id: Uuid,
+    file_path: String,
+    created_at: DateTime<Utc>,
+}
+
+/// Stuck-state janitor
+pub struct StuckStateJanitor {
+    pool: PgPool,
+    settings_service: Arc<SettingsService>,
+}
+
+impl StuckStateJanitor {
+    /// Create a new janitor
+    pub fn new(pool: PgPool, settings_service: Arc<SettingsService>) -> Self {
+        Self {
+            pool,
+            settings_service,
+        }
+    }
+
+    /// Load janitor configuration from database settings
+    async fn load_config(&self) -> JanitorConfig {
+        let mut config = JanitorConfig::default();
+
+        if let Ok(Some(setting)) = self.settings_service.get_setting("janitor_enabled").await {
+            if let Some(value) = setting.setting_value.as_bool() {
+                config.enabled = value;
+            }
+        }
+
+        if let Ok(Some(setting)) = self
+            .settings_service
+            .get_setting("janitor_interval_minutes")
+            .await
+        {
+            if let Some(value) = setting.setting_value.as_i64() {
+                config.interval_minutes = value.max(1);
+            }
+        }
+
+        if let Ok(Some(setting)) = self
+            .settings_service
+            .get_setting("janitor_notification_stuck_minutes")
+            .await
+        {
+            if let Some(value) = setting.setting_value.as_i64() {
+                config.notification_stuck_minutes = value.max(1);
+            }
+        }
+
+        if let Ok(Some(setting)) = self
+            .settings_service
+            .get_setting("janitor_document_stuck_minutes")
+            .await
+        {
+            if let Some(value) = setting.setting_value.as_i64() {
+                config.document_stuck_minutes = value.max(1);
+            }
+        }
+
+        config
+    }
+
+    /// Start the background janitor loop
+    pub async fn start(self: Arc<Self>) {
+        info!("Starting stuck-state janitor background task");
+
+        loop {
+            let config = self.load_config().await;
+
+            if config.enabled {
+                let span = info_span!("janitor_run", run_id = %Uuid::new_v4());
+                async {
+                    match self.run(&config).await {
+                        Ok(report) if report.total() > 0 => warn!(
+                            "Janitor recovered {} stuck rows ({} notifications, {} generating documents, {} missing files)",
+                            report.total(),
+                            report.notifications_failed,
+                            report.documents_failed,
+                            report.missing_files_failed
+                        ),
+                        Ok(_) => {}
+                        Err(e) => error!("Janitor run failed: {}", e),
+                    }
+                }
+                .instrument(span)
+                .await;
+            }
+
+            sleep(TokioDuration::from_secs(config.interval_minutes as u64 * 60)).await;
+        }
+    }
+
+    /// Run all janitor checks once
+    pub async fn run(&self, config: &JanitorConfig) -> Result<JanitorReport> {
+        Ok(JanitorReport {
+            notifications_failed: self
+                .fail_stuck_notifications(config.notification_stuck_minutes)
+                .await?,
+            documents_failed: self
+                .fail_stuck_documents(config.document_stuck_minutes)
+                .await?,
+            missing_files_failed: self
+                .fail_documents_missing_files(config.document_stuck_minutes)
+                .await?,
+        })
+    }
+
+    /// Fail notifications stuck in PROCESSING
+    ///
+    /// Rows whose lease is still live belong to a worker that is heartbeating,
+    /// so they are left alone regardless of age.
+    async fn fail_stuck_notifications(&self, stuck_minutes: i64) -> Result<usize> {
+        let mut tx = self.pool.begin().await?;
+        apply_rls_context(&mut tx, SYSTEM_USER_ID, SYSTEM_ROLE).await?;
+
+        let stuck = sqlx::query_as::<_, StuckNotification>(
+            r#"
+            WITH stuck AS (
+                SELECT id, claimed_by, updated_at
+                FROM notification_queue
+                WHERE status = 'PROCESSING'
+                  AND updated_at < NOW() - make_interval(mins => $1::int)
+                  AND (lease_expires_at IS NULL OR lease_expires_at < NOW())
+                FOR UPDATE SKIP LOCKED
+            )
+            UPDATE notification_queue n
+            SET status = 'FAILED',
+                error_code = $2,
+                error_message = 'Stuck in PROCESSING since '
+                    || to_char(stuck.updated_at AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS') || ' UTC'
+                    || COALESCE(' (worker ' || stuck.claimed_by || ')', '')
+                    || '; marked FAILED by janitor',
+                claimed_by = NULL,
+                claimed_at = NULL,
+                lease_expires_at = NULL
+            FROM stuck
+            WHERE n.id = stuck.id
+            RETURNING n.id, stuck.claimed_by, stuck.updated_at AS stuck_since
+            "#,
+        )
+        .bind(stuck_minutes)
+        .bind(STUCK_NOTIFICATION_ERROR_CODE)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        for notification in &stuck {
+            error!(
+                notification_id = %notification.id,
+                claimed_by = ?notification.claimed_by,
+                "Notification stuck in PROCESSING marked FAILED"
+            );
+            self.raise_alert(CreateSystemAlert {
+                alert_type: "STUCK_NOTIFICATION",
+                severity: AlertSeverity::Warning,
+                source: ALERT_SOURCE,
+                entity_type: Some("NOTIFICATION"),
+                entity_id: Some(notification.id),
+                message: format!(
+                    "Notification stuck in PROCESSING for more than {} minutes was marked FAILED",
+                    stuck_minutes
+                ),
+                details: Some(serde_json::json!({
+                    "claimed_by": notification.claimed_by,
+                    "stuck_since": notification.stuck_since,
+                })),
+            })
+            .await;
+        }
+
+        Ok(stuck.len())
+    }
+
+    /// Fail documents stuck in GENERATING
+    async fn fail_stuck_documents(&self, stuck_minutes: i64) -> Result<usize> {
+        let candidates = self
+            .find_documents(
+                r#"
+                SELECT id, provider_id, file_path, created_at
+                FROM generated_documents
+                WHERE status = 'GENERATING'
+                  AND created_at < NOW() - make_interval(mins => $1::int)
+                ORDER BY created_at
+                "#,
+                stuck_minutes,
+            )
+            .await?;
+
+        let mut failed = 0;
+        for doc in candidates {
+            let reason = format!(
+                "Generation did not complete within {} minutes (started {}); marked FAILED by janitor",
+                stuck_minutes,
+                doc.created_at.format("%Y-%m-%d %H:%M:%S UTC")
+            );
+            if self.fail_document(&doc, "GENERATING", &reason).await? {
+                failed += 1;
+                self.raise_alert(CreateSystemAlert {
+                    alert_type: "STUCK_DOCUMENT",
+                    severity: AlertSeverity::Warning,
+                    source: ALERT_SOURCE,
+                    entity_type: Some("DOCUMENT"),
+                    entity_id: Some(doc.id),
+                    message: reason,
+                    details: Some(serde_json::json!({
+                        "provider_id": doc.provider_id,
+                        "created_at": doc.created_at,
+                    })),
+                })
+                .await;
+            }
+        }
+
+        Ok(failed)
+    }
+
+    /// Fail GENERATED documents whose PDF is missing from storage
+    ///
+    /// Documents younger than the GENERATING threshold are skipped so a file
+    /// that is still being written is never mistaken for a missing one.
+    async fn fail_documents_missing_files(&self, grace_minutes: i64) -> Result<usize> {
+        let candidates = self
+            .find_documents(
+                &format!(
+                    r#"
+                    SELECT id, provider_id, file_path, created_at
+                    FROM generated_documents
+                    WHERE status = 'GENERATED'
+                      AND created_at < NOW() - make_interval(mins => $1::int)
+                    ORDER BY created_at DESC
+                    LIMIT {}
+                    "#,
+                    DOCUMENT_FILE_SCAN_LIMIT
+                ),
+                grace_minutes,
+            )
+            .await?;
+
+        let mut failed = 0;
+        for doc in candidates {
+            if tokio::fs::try_exists(&doc.file_path).await.unwrap_or(true) {
+                continue;
+            }
+
+            let reason = format!(
+                "Document file is missing from storage ({}); marked FAILED by janitor",
+                doc.file_path
+            );
+            if self.fail_document(&doc, "GENERATED", &reason).await? {
+                failed += 1;
+                self.raise_alert(CreateSystemAlert {
+                    alert_type: "DOCUMENT_FILE_MISSING",
+                    severity: AlertSeverity::Critical,
+                    source: ALERT_SOURCE,
+                    entity_type: Some("DOCUMENT"),
+                    entity_id: Some(doc.id),
+                    message: "Generated document has no file in storage and was marked FAILED"
+                        .to_string(),
+                    details: Some(serde_json::json!({
+                        "provider_id": doc.provider_id,
+                        "file_path": doc.file_path,
+                        "created_at": doc.created_at,
+                    })),
+                })
+                .await;
+            }
+        }
+
+        Ok(failed)
+    }
+
+    /// Load document candidates as the system user
+    async fn find_documents(&self, sql: &str, minutes: i64) -> Result<Vec<DocumentCandidate>> {
+        let mut tx = self.pool.begin().await?;
+        apply_rls_context(&mut tx, SYSTEM_USER_ID, SYSTEM_ROLE).await?;
+
+        let docs = sqlx::query_as::<_, DocumentCandidate>(sql)
+            .bind(minutes)
+            .fetch_all(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(docs)
+    }
+
+    /// Move a single document to FAILED
+    ///
+    /// Documents can only be updated by their provider (RLS), so the update
+    /// runs in the provider's context. Returns false if the document changed
+    /// status in the meantime.
+    async fn fail_document(
+        &self,
+        doc: &DocumentCandidate,
+        expected_status: &str,
+        reason: &str,
+    ) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+        apply_rls_context(&mut tx, doc.provider_id, "DOCTOR").await?;
+
+        let result = sqlx::query(
+            r#"
+            UPDATE generated_documents
+            SET status = 'FAILED', generation_error = $3
+            WHERE id = $1 AND status = $2
+            "#,
+        )
+        .bind(doc.id)
+        .bind(expected_status)
+        .bind(reason)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        if result.rows_affected() > 0 {
+            error!(document_id = %doc.id, "{}", reason);
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Record an internal alert; failures are logged, never propagated
+    async fn raise_alert(&self, alert: CreateSystemAlert) {
+        if let Err(e) = SystemAlert::create(&self.pool, alert).await {
+            error!("Failed to record system alert: {}", e);
+        }
+    }
+}
+
+/// Spawn the janitor as a background task
+pub fn spawn_janitor(pool: PgPool, settings_service: Arc<SettingsService>) {
+    let janitor = Arc::new(StuckStateJanitor::new(pool, settings_service));
+
+    tokio::spawn(async move {
+        janitor.start().await;
+    });
+
+    info!("Stuck-state janitor spawned as background task");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_janitor_config_default() {
+        let config = JanitorConfig::default();
+        assert!(config.enabled);
+        assert_eq!(config.interval_minutes, 10);
+        assert_eq!(config.notification_stuck_minutes, 30);
+        assert_eq!(config.document_stuck_minutes, 15);
+    }
+
+    #[test]
+    fn test_notification_threshold_exceeds_lease() {
+        // A notification must not be failed while its worker could still be
+        // sending it under a valid lease
+        let config = JanitorConfig::default();
+        assert!(
+            config.notification_stuck_minutes * 60
+                > crate::services::notification_service::LEASE_DURATION_SECS as i64
+        );
+    }
+
+    #[test]
+    fn test_janitor_report_total() {
+        let report = JanitorReport {
+            notifications_failed: 2,
+            documents_failed: 1,
+            missing_files_failed: 3,
+        };
+        assert_eq!(report.total(), 6);
+        assert_eq!(JanitorReport::default().total(), 0);
+    }
+}
@@ -2,16 +2,20 @@
  * JWT Service
  *
  * Handles JWT token generation, validation, and refresh token management.
- * Uses HS256 algorithm for signing tokens.
+ * Access tokens are signed with HS256 by default, or with an asymmetric key
+ * ring (RS256/EdDSA, `kid` header) so other services can verify them against
+ * the published JWKS. Refresh tokens are only consumed by DocPat and always
+ * use HS256 with the refresh secret.
  */
 
 use chrono::{Duration, Utc};
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use jsonwebtoken::{decode, decode_header, encode, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::config::JwtConfig;
 use crate::models::UserRole;
+use crate::utils::jwt_keys::JwkSet;
 use crate::utils::{AppError, Result};
 
 /// JWT claims structure
@@ -29,6 +33,79 @@ pub struct Claims {
     pub token_type: String,
     /// JWT ID - unique identifier for this token
     pub jti: String,
+    /// Actor (RFC 8693) - the admin acting as `sub` during impersonation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub act: Option<ActorClaim>,
+}
+
+impl Claims {
+    /// Impersonating admin, if this is an impersonation token
+    pub fn impersonator_id(&self) -> Option<Uuid> {
+        self.act.as_ref().and_then(|act| Uuid::parse_str(&act.sub).ok())
+    }
+}
+
+/// Actor claim carried by impersonation tokens
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ActorClaim {
+    /// Impersonating admin's user ID
+    pub sub: String,
+}
+
+/// Trusted device token claims (stored in the trusted device cookie)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DeviceClaims {
+    /// Subject (user ID)
+    pub sub: String,
+    /// Trusted device ID (trusted_devices.id)
+    pub did: String,
+    /// Issued at (Unix timestamp)
+    pub iat: i64,
+    /// Expiration time (Unix timestamp)
+    pub exp: i64,
+    /// Token type (always "device")
+    pub token_type: String,
+}
+
+/// Patient portal token claims
+///
+/// Portal tokens authenticate a patient, not a staff user: they have no role
+/// and are only accepted on `/api/portal/v1`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PortalClaims {
+    /// Subject (patient ID)
+    pub sub: String,
+    /// Portal session ID (patient_portal_sessions.id)
+    pub sid: String,
+    /// Issued at (Unix timestamp)
+    pub iat: i64,
+    /// Expiration time (Unix timestamp)
+    pub exp: i64,
+    /// Token type (always "portal")
+    pub token_type: String,
+}
+
+/// Online booking slot token claims
+///
+/// Issued with every slot offered by the public booking API, so a booking
+/// request can only name a slot the practice offered. Not an
+/// authentication token.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BookingSlotClaims {
+    /// Subject (provider ID)
+    pub sub: String,
+    /// Slot start (Unix timestamp)
+    pub start: i64,
+    /// Slot length in minutes
+    pub dur: i32,
+    /// Appointment type (e.g. "FOLLOW_UP")
+    pub typ: String,
+    /// Issued at (Unix timestamp)
+    pub iat: i64,
+    /// Expiration time (Unix timestamp)
+    pub exp: i64,
+    /// Token type (always "booking_slot")
+    pub token_type: String,
 }
 
 /// Token pair containing access and refresh tokens
@@ -104,13 +181,62 @@ impl JwtService {
             exp: expiry.timestamp(),
             token_type: "access".to_string(),
             jti: Uuid::new_v4().to_string(), // Unique token identifier
+            act: None,
         };
 
-        let token = encode(
-            &Header::default(),
-            &claims,
-            &EncodingKey::from_secret(self.config.secret.as_bytes()),
-        )?;
+        self.encode_access_claims(&claims)
+    }
+
+    /// Generate an impersonation access token
+    ///
+    /// The token authenticates as `user_id` and carries the admin in the `act`
+    /// claim. No refresh token is issued, so the session ends at `expires_at`.
+    ///
+    /// # Returns
+    ///
+    /// The token and its claims (the `jti` identifies the impersonation session)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if token generation fails
+    pub fn generate_impersonation_token(
+        &self,
+        user_id: &Uuid,
+        role: &UserRole,
+        impersonator_id: &Uuid,
+        expires_at: chrono::DateTime<Utc>,
+    ) -> Result<(String, Claims)> {
+        let claims = Claims {
+            sub: user_id.to_string(),
+            role: role.to_string(),
+            iat: Utc::now().timestamp(),
+            exp: expires_at.timestamp(),
+            token_type: "access".to_string(),
+            jti: Uuid::new_v4().to_string(),
+            act: Some(ActorClaim {
+                sub: impersonator_id.to_string(),
+            }),
+        };
+
+        let token = self.encode_access_claims(&claims)?;
+        Ok((token, claims))
+    }
+
+    /// Sign access token claims with the key ring or the HS256 secret
+    fn encode_access_claims(&self, claims: &Claims) -> Result<String> {
+        let token = match &self.config.key_ring {
+            Some(key_ring) => {
+                let (kid, key) = key_ring.signing_key();
+                let mut header = Header::new(key_ring.algorithm());
+                header.kid = Some(kid.to_string());
+                encode(&header, claims, key)?
+            }
+            None => encode(
+                &Header::default(),
+                claims,
+                &EncodingKey::from_secret(self.config.secret.as_bytes()),
+            )?,
+        };
 
         Ok(token)
     }
@@ -136,6 +262,7 @@ impl JwtService {
             exp: expiry.timestamp(),
             token_type: "refresh".to_string(),
             jti: Uuid::new_v4().to_string(), // Unique token identifier
+            act: None,
         };
 
         let token = encode(
@@ -161,11 +288,26 @@ impl JwtService {
     ///
     /// Returns Unauthorized error if token is invalid, expired, or malformed
     pub fn validate_access_token(&self, token: &str) -> Result<Claims> {
-        let token_data = decode::<Claims>(
-            token,
-            &DecodingKey::from_secret(self.config.secret.as_bytes()),
-            &Validation::default(),
-        )
+        let token_data = match &self.config.key_ring {
+            Some(key_ring) => {
+                // Pick the verification key by kid; tokens signed with a key
+                // that has been removed from the ring are rejected
+                let kid = decode_header(token)
+                    .ok()
+                    .and_then(|header| header.kid)
+                    .ok_or_else(|| AppError::Unauthorized("Invalid or expired token".to_string()))?;
+                let key = key_ring.decoding_key(&kid).ok_or_else(|| {
+                    tracing::warn!("Access token signed with unknown key id: {}", kid);
+                    AppError::Unauthorized("Invalid or expired token".to_string())
+                })?;
+                decode::<Claims>(token, key, &Validation::new(key_ring.algorithm()))
+            }
+            None => decode::<Claims>(
+                token,
+                &DecodingKey::from_secret(self.config.secret.as_bytes()),
+                &Validation::default(),
+            ),
+        }
         .map_err(|e| {
             tracing::warn!("Invalid access token: {:?}", e);
             AppError::Unauthorized("Invalid or expired token".to_string())
@@ -211,6 +353,172 @@ impl JwtService {
         Ok(token_data.claims)
     }
 
+    /// Generate a trusted device token
+    ///
+    /// Signed with the refresh secret; the `device` token type keeps it from
+    /// being accepted as an access or refresh token.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if token generation fails
+    pub fn generate_device_token(
+        &self,
+        user_id: &Uuid,
+        device_id: &Uuid,
+        expires_at: chrono::DateTime<Utc>,
+    ) -> Result<String> {
+        let claims = DeviceClaims {
+            sub: user_id.to_string(),
+            did: device_id.to_string(),
+            iat: Utc::now().timestamp(),
+            exp: expires_at.timestamp(),
+            token_type: "device".to_string(),
+        };
+
+        let token = encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(self.config.refresh_secret.as_bytes()),
+        )?;
+
+        Ok(token)
+    }
+
+    /// Validate and decode a trusted device token
+    ///
+    /// # Errors
+    ///
+    /// Returns Unauthorized error if the token is invalid, expired or not a device token
+    pub fn validate_device_token(&self, token: &str) -> Result<DeviceClaims> {
+        let token_data = decode::<DeviceClaims>(
+            token,
+            &DecodingKey::from_secret(self.config.refresh_secret.as_bytes()),
+            &Validation::default(),
+        )
+        .map_err(|e| {
+            tracing::warn!("Invalid trusted device token: {:?}", e);
+            AppError::Unauthorized("Invalid or expired device token".to_string())
+        })?;
+
+        if token_data.claims.token_type != "device" {
+            return Err(AppError::Unauthorized("Invalid token type".to_string()));
+        }
+
+        Ok(token_data.claims)
+    }
+
+    /// Generate a patient portal token
+    ///
+    /// Signed with the refresh secret like device tokens; the `portal` token
+    /// type keeps it from being accepted as a staff access or refresh token.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if token generation fails
+    pub fn generate_portal_token(
+        &self,
+        patient_id: &Uuid,
+        session_id: &Uuid,
+        expires_at: chrono::DateTime<Utc>,
+    ) -> Result<String> {
+        let claims = PortalClaims {
+            sub: patient_id.to_string(),
+            sid: session_id.to_string(),
+            iat: Utc::now().timestamp(),
+            exp: expires_at.timestamp(),
+            token_type: "portal".to_string(),
+        };
+
+        let token = encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(self.config.refresh_secret.as_bytes()),
+        )?;
+
+        Ok(token)
+    }
+
+    /// Validate and decode a patient portal token
+    ///
+    /// # Errors
+    ///
+    /// Returns Unauthorized error if the token is invalid, expired or not a portal token
+    pub fn validate_portal_token(&self, token: &str) -> Result<PortalClaims> {
+        let token_data = decode::<PortalClaims>(
+            token,
+            &DecodingKey::from_secret(self.config.refresh_secret.as_bytes()),
+            &Validation::default(),
+        )
+        .map_err(|e| {
+            tracing::warn!("Invalid portal token: {:?}", e);
+            AppError::Unauthorized("Invalid or expired portal token".to_string())
+        })?;
+
+        if token_data.claims.token_type != "portal" {
+            return Err(AppError::Unauthorized("Invalid token type".to_string()));
+        }
+
+        Ok(token_data.claims)
+    }
+
+    /// Generate an online booking slot token
+    ///
+    /// Signed with the refresh secret like portal tokens; the `booking_slot`
+    /// token type keeps it from being accepted anywhere else.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if token generation fails
+    pub fn generate_booking_slot_token(
+        &self,
+        provider_id: &Uuid,
+        start: chrono::DateTime<Utc>,
+        duration_minutes: i32,
+        appointment_type: &str,
+        expires_at: chrono::DateTime<Utc>,
+    ) -> Result<String> {
+        let claims = BookingSlotClaims {
+            sub: provider_id.to_string(),
+            start: start.timestamp(),
+            dur: duration_minutes,
+            typ: appointment_type.to_string(),
+            iat: Utc::now().timestamp(),
+            exp: expires_at.timestamp(),
+            token_type: "booking_slot".to_string(),
+        };
+
+        let token = encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(self.config.refresh_secret.as_bytes()),
+        )?;
+
+        Ok(token)
+    }
+
+    /// Validate and decode an online booking slot token
+    ///
+    /// # Errors
+    ///
+    /// Returns BadRequest error if the token is invalid, expired or not a slot token
+    pub fn validate_booking_slot_token(&self, token: &str) -> Result<BookingSlotClaims> {
+        let token_data = decode::<BookingSlotClaims>(
+            token,
+            &DecodingKey::from_secret(self.config.refresh_secret.as_bytes()),
+            &Validation::default(),
+        )
+        .map_err(|e| {
+            tracing::warn!("Invalid booking slot token: {:?}", e);
+            AppError::BadRequest("This slot is no longer offered, please pick another one".to_string())
+        })?;
+
+        if token_data.claims.token_type != "booking_slot" {
+            return Err(AppError::BadRequest("Invalid slot".to_string()));
+        }
+
+        Ok(token_data.claims)
+    }
+
     /// Refresh an access token using a valid refresh token
     ///
     /// # Arguments
@@ -233,16 +541,34 @@ impl JwtService {
         let role = match claims.role.as_str() {
             "ADMIN" => UserRole::Admin,
             "DOCTOR" => UserRole::Doctor,
+            "NURSE" => UserRole::Nurse,
+            "RECEPTIONIST" => UserRole::Receptionist,
             _ => return Err(AppError::Unauthorized("Invalid role in token".to_string())),
         };
 
         self.generate_tokens(&user_id, &role)
     }
+
+    /// Public keys for verifying access tokens
+    ///
+    /// Empty when access tokens are signed with the shared HS256 secret.
+    pub fn jwks(&self) -> JwkSet {
+        match &self.config.key_ring {
+            Some(key_ring) => key_ring.jwks(),
+            None => JwkSet { keys: Vec::new() },
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::jwt_keys::tests::{
+        ed25519_key, ED25519_NEXT_PRIVATE_PEM, ED25519_NEXT_PUBLIC_PEM, ED25519_PRIVATE_PEM,
+        ED25519_PUBLIC_PEM,
+    };
+    use crate::utils::jwt_keys::{JwtAlgorithm, JwtKeyPem, JwtKeyRing};
+    use std::sync::Arc;
 
     fn test_jwt_config() -> JwtConfig {
         JwtConfig {
@@ -250,9 +576,120 @@ mod tests {
             refresh_secret: "test_refresh_secret_key_minimum_32_characters_long".to_string(),
             access_token_expiry: 1800, // 30 minutes
             refresh_token_expiry: 604800, // 7 days
+            key_ring: None,
+        }
+    }
+
+    fn eddsa_jwt_config(active_kid: &str, keys: Vec<JwtKeyPem>) -> JwtConfig {
+        JwtConfig {
+            key_ring: Some(Arc::new(
+                JwtKeyRing::from_pem_keys(JwtAlgorithm::EdDsa, active_kid, keys).unwrap(),
+            )),
+            ..test_jwt_config()
         }
     }
 
+    #[test]
+    fn test_device_token_roundtrip() {
+        let jwt_service = JwtService::new(test_jwt_config());
+        let user_id = Uuid::new_v4();
+        let device_id = Uuid::new_v4();
+
+        let token = jwt_service
+            .generate_device_token(&user_id, &device_id, Utc::now() + Duration::days(30))
+            .unwrap();
+        let claims = jwt_service.validate_device_token(&token).unwrap();
+        assert_eq!(claims.sub, user_id.to_string());
+        assert_eq!(claims.did, device_id.to_string());
+
+        // Device tokens are not refresh tokens, and refresh tokens are not device tokens
+        assert!(jwt_service.validate_refresh_token(&token).is_err());
+        let tokens = jwt_service.generate_tokens(&user_id, &UserRole::Doctor).unwrap();
+        assert!(jwt_service.validate_device_token(&tokens.refresh_token).is_err());
+    }
+
+    #[test]
+    fn test_expired_device_token_rejected() {
+        let jwt_service = JwtService::new(test_jwt_config());
+        let token = jwt_service
+            .generate_device_token(&Uuid::new_v4(), &Uuid::new_v4(), Utc::now() - Duration::days(1))
+            .unwrap();
+        assert!(jwt_service.validate_device_token(&token).is_err());
+    }
+
+    #[test]
+    fn test_portal_token_isolated_from_staff_tokens() {
+        let jwt_service = JwtService::new(test_jwt_config());
+        let patient_id = Uuid::new_v4();
+        let session_id = Uuid::new_v4();
+
+        let token = jwt_service
+            .generate_portal_token(&patient_id, &session_id, Utc::now() + Duration::minutes(30))
+            .unwrap();
+        let claims = jwt_service.validate_portal_token(&token).unwrap();
+        assert_eq!(claims.sub, patient_id.to_string());
+        assert_eq!(claims.sid, session_id.to_string());
+
+        // Portal tokens are not staff tokens, and staff tokens are not portal tokens
+        assert!(jwt_service.validate_access_token(&token).is_err());
+        assert!(jwt_service.validate_refresh_token(&token).is_err());
+        assert!(jwt_service.validate_device_token(&token).is_err());
+        let tokens = jwt_service.generate_tokens(&Uuid::new_v4(), &UserRole::Admin).unwrap();
+        assert!(jwt_service.validate_portal_token(&tokens.access_token).is_err());
+        assert!(jwt_service.validate_portal_token(&tokens.refresh_token).is_err());
+
+        let expired = jwt_service
+            .generate_portal_token(&patient_id, &session_id, Utc::now() - Duration::minutes(5))
+            .unwrap();
+        assert!(jwt_service.validate_portal_token(&expired).is_err());
+    }
+
+    #[test]
+    fn test_booking_slot_token_round_trip() {
+        let jwt_service = JwtService::new(test_jwt_config());
+        let provider_id = Uuid::new_v4();
+        let start = Utc::now() + Duration::days(2);
+
+        let token = jwt_service
+            .generate_booking_slot_token(&provider_id, start, 30, "FOLLOW_UP", Utc::now() + Duration::hours(1))
+            .unwrap();
+        let claims = jwt_service.validate_booking_slot_token(&token).unwrap();
+        assert_eq!(claims.sub, provider_id.to_string());
+        assert_eq!(claims.start, start.timestamp());
+        assert_eq!(claims.dur, 30);
+        assert_eq!(claims.typ, "FOLLOW_UP");
+
+        // Slot tokens authenticate nothing
+        assert!(jwt_service.validate_access_token(&token).is_err());
+        assert!(jwt_service.validate_portal_token(&token).is_err());
+        let portal = jwt_service
+            .generate_portal_token(&Uuid::new_v4(), &Uuid::new_v4(), Utc::now() + Duration::minutes(30))
+            .unwrap();
+        assert!(jwt_service.validate_booking_slot_token(&portal).is_err());
+    }
+
+    #[test]
+    fn test_impersonation_token_carries_actor() {
+        let jwt_service = JwtService::new(test_jwt_config());
+        let user_id = Uuid::new_v4();
+        let admin_id = Uuid::new_v4();
+
+        let (token, issued) = jwt_service
+            .generate_impersonation_token(&user_id, &UserRole::Doctor, &admin_id, Utc::now() + Duration::minutes(30))
+            .unwrap();
+        let claims = jwt_service.validate_access_token(&token).unwrap();
+        assert_eq!(claims.sub, user_id.to_string());
+        assert_eq!(claims.role, "DOCTOR");
+        assert_eq!(claims.jti, issued.jti);
+        assert_eq!(claims.impersonator_id(), Some(admin_id));
+
+        // Regular access tokens have no actor
+        let tokens = jwt_service.generate_tokens(&user_id, &UserRole::Doctor).unwrap();
+        let claims = jwt_service.validate_access_token(&tokens.access_token).unwrap();
+        assert_eq!(claims.act, None);
+        assert_eq!(claims.impersonator_id(), None);
+    }
+
     #[test]
     fn test_generate_tokens() {
         let jwt_service = JwtService::new(test_jwt_config());
@@ -338,4 +775,80 @@ mod tests {
         let result = jwt_service.validate_refresh_token(&tokens.access_token);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_hs256_publishes_no_jwks() {
+        let jwt_service = JwtService::new(test_jwt_config());
+        assert!(jwt_service.jwks().keys.is_empty());
+    }
+
+    #[test]
+    fn test_asymmetric_access_token_has_kid() {
+        let jwt_service = JwtService::new(eddsa_jwt_config(
+            "k1",
+            vec![ed25519_key("k1", ED25519_PUBLIC_PEM, Some(ED25519_PRIVATE_PEM))],
+        ));
+        let user_id = Uuid::new_v4();
+
+        let tokens = jwt_service.generate_tokens(&user_id, &UserRole::Doctor).unwrap();
+        let header = decode_header(&tokens.access_token).unwrap();
+        assert_eq!(header.alg, jsonwebtoken::Algorithm::EdDSA);
+        assert_eq!(header.kid.as_deref(), Some("k1"));
+
+        let claims = jwt_service.validate_access_token(&tokens.access_token).unwrap();
+        assert_eq!(claims.sub, user_id.to_string());
+
+        // Refresh tokens keep using the shared refresh secret
+        assert!(jwt_service.refresh_access_token(&tokens.refresh_token).is_ok());
+    }
+
+    #[test]
+    fn test_key_rotation_overlap() {
+        let old_service = JwtService::new(eddsa_jwt_config(
+            "k1",
+            vec![ed25519_key("k1", ED25519_PUBLIC_PEM, Some(ED25519_PRIVATE_PEM))],
+        ));
+        let old_token = old_service
+            .generate_tokens(&Uuid::new_v4(), &UserRole::Admin)
+            .unwrap()
+            .access_token;
+
+        // k2 now signs; k1 is kept (public only) until its tokens expire
+        let rotated = JwtService::new(eddsa_jwt_config(
+            "k2",
+            vec![
+                ed25519_key("k1", ED25519_PUBLIC_PEM, None),
+                ed25519_key("k2", ED25519_NEXT_PUBLIC_PEM, Some(ED25519_NEXT_PRIVATE_PEM)),
+            ],
+        ));
+        assert!(rotated.validate_access_token(&old_token).is_ok());
+        let new_token = rotated
+            .generate_tokens(&Uuid::new_v4(), &UserRole::Admin)
+            .unwrap()
+            .access_token;
+        assert_eq!(decode_header(&new_token).unwrap().kid.as_deref(), Some("k2"));
+        assert_eq!(rotated.jwks().keys.len(), 2);
+
+        // Once k1 is removed, its tokens are rejected
+        let retired = JwtService::new(eddsa_jwt_config(
+            "k2",
+            vec![ed25519_key("k2", ED25519_NEXT_PUBLIC_PEM, Some(ED25519_NEXT_PRIVATE_PEM))],
+        ));
+        assert!(retired.validate_access_token(&old_token).is_err());
+        assert!(retired.validate_access_token(&new_token).is_ok());
+    }
+
+    #[test]
+    fn test_asymmetric_rejects_hs256_token() {
+        let hs256_token = JwtService::new(test_jwt_config())
+            .generate_tokens(&Uuid::new_v4(), &UserRole::Doctor)
+            .unwrap()
+            .access_token;
+
+        let jwt_service = JwtService::new(eddsa_jwt_config(
+            "k1",
+            vec![ed25519_key("k1", ED25519_PUBLIC_PEM, Some(ED25519_PRIVATE_PEM))],
+        ));
+        assert!(jwt_service.validate_access_token(&hs256_token).is_err());
+    }
 }
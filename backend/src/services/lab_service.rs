@@ -0,0 +1,898 @@
+/*!
+ * Lab Service
+ *
+ * Lab orders and LOINC-coded results, under RLS (doctors order and edit,
+ * nurses read and record results). Result values, notes and the clinical
+ * information of an order are encrypted, so trends are computed after
+ * decryption.
+ *
+ * Orders and results are medical records: deleting one only marks it
+ * deleted.
+ *
+ * Recording a result against an order moves the order from ORDERED to
+ * PARTIAL, and to COMPLETED once every requested test has a result. Tests are
+ * picked from `lab_test_catalog`; the requisition PDF itself is rendered by
+ * `DocumentService::generate_lab_requisition`.
+ */
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    db::rls::begin_with_rls,
+    models::{
+        lab_result::{parse_numeric_value, validate_loinc_code},
+        AbnormalFlag, CreateLabOrderRequest, CreateLabResultRequest, LabCatalogQuery,
+        LabCatalogTest, LabOrder, LabOrderResponse, LabOrderStatus, LabOrderTest, LabResult, LabResultResponse, LabTrend, LabTrendQuery,
+        ListLabOrdersQuery, ListLabResultsQuery, UpdateLabOrderRequest, UpdateLabResultRequest,
+    },
+    utils::{encryption::EncryptionKey, AppError, Result},
+};
+use chrono::NaiveDate;
+use sqlx::{PgConnection, PgPool};
+use tracing::info;
+use uuid::Uuid;
+
+const ORDER_COLUMNS: &str = r#"
+    id, patient_id, visit_id, visit_date, status, urgency, fasting, lab_name, clinical_info,
+    requisition_number, requisition_document_id, ordered_at, created_by, created_at, updated_by,
+    updated_at
+"#;
+
+const RESULT_COLUMNS: &str = r#"
+    id, patient_id, order_id, visit_id, visit_date, loinc_code, test_name, value, unit,
+    reference_low, reference_high, reference_text, abnormal_flag, collected_at, resulted_at,
+    notes, created_by, created_at, updated_by, updated_at
+"#;
+
+/// Lab service
+pub struct LabService {
+    pool: PgPool,
+    encryption_key: EncryptionKey,
+}
+
+impl LabService {
+    /// Create a new lab service
+    pub fn new(pool: PgPool, encryption_key: EncryptionKey) -> Self {
+        Self {
+            pool,
+            encryption_key,
+        }
+    }
+
+    /// Place a lab order
+    ///
+    /// Fails with Validation for an invalid or repeated LOINC code or a visit
+    /// that is not the patient's, and with Conflict if the patient is
+    /// anonymized or merged.
+    pub async fn create_order(
+        &self,
+        patient_id: Uuid,
+        req: &CreateLabOrderRequest,
+        user_id: Uuid,
+    ) -> Result<LabOrderResponse> {
+        let mut codes = Vec::with_capacity(req.tests.len());
+        let mut names = Vec::with_capacity(req.tests.len());
+        let mut seen = HashSet::new();
+        for test in &req.tests {
+            let code = test.loinc_code.trim().to_string();
+            validate_loinc_code(&code).map_err(|e| AppError::Validation(e.to_string()))?;
+            if !seen.insert(code.clone()) {
+                return Err(AppError::Validation(format!(
+                    "Test {} is requested twice",
+                    code
+                )));
+            }
+            codes.push(code);
+            names.push(test.test_name.trim().to_string());
+        }
+
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        self.check_patient(&mut tx, patient_id).await?;
+
+        let visit_date = match req.visit_id {
+            Some(visit_id) => Some(self.visit_date(&mut tx, patient_id, visit_id).await?),
+            None => None,
+        };
+
+        let order = sqlx::query_as::<_, LabOrder>(&format!(
+            r#"
+            INSERT INTO lab_orders (
+                patient_id, visit_id, visit_date, urgency, fasting, lab_name, clinical_info,
+                created_by
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING {}
+            "#,
+            ORDER_COLUMNS
+        ))
+        .bind(patient_id)
+        .bind(req.visit_id)
+        .bind(visit_date)
+        .bind(req.urgency.as_str())
+        .bind(req.fasting)
+        .bind(req.lab_name.as_deref().map(str::trim))
+        .bind(self.encrypt_optional(&req.clinical_info)?)
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO lab_order_tests (order_id, loinc_code, test_name, position)
+            SELECT $1, t.loinc_code, t.test_name, t.position::INTEGER
+            FROM UNNEST($2::TEXT[], $3::TEXT[])
+                 WITH ORDINALITY AS t(loinc_code, test_name, position)
+            "#,
+        )
+        .bind(order.id)
+        .bind(&codes)
+        .bind(&names)
+        .execute(&mut *tx)
+        .await?;
+
+        let tests = self.order_tests(&mut tx, &[order.id]).await?;
+        tx.commit().await?;
+
+        info!(
+            "Lab order {} ({} tests) placed for patient {} by {}",
+            order.id,
+            tests.len(),
+            patient_id,
+            user_id
+        );
+
+        self.decrypt_order(&order, tests)
+    }
+
+    /// List a patient's lab orders, newest first
+    pub async fn list_orders(
+        &self,
+        patient_id: Uuid,
+        query: &ListLabOrdersQuery,
+        user_id: Uuid,
+    ) -> Result<Vec<LabOrderResponse>> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        let orders = sqlx::query_as::<_, LabOrder>(&format!(
+            r#"
+            SELECT {}
+            FROM lab_orders
+            WHERE deleted_at IS NULL
+              AND patient_id = $1
+              AND ($2::TEXT IS NULL OR status = $2)
+              AND ($3::UUID IS NULL OR visit_id = $3)
+            ORDER BY ordered_at DESC
+            "#,
+            ORDER_COLUMNS
+        ))
+        .bind(patient_id)
+        .bind(query.status.map(|s| s.as_str()))
+        .bind(query.visit_id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let ids: Vec<Uuid> = orders.iter().map(|o| o.id).collect();
+        let mut tests_by_order: HashMap<Uuid, Vec<LabOrderTest>> = HashMap::new();
+        for test in self.order_tests(&mut tx, &ids).await? {
+            tests_by_order.entry(test.order_id).or_default().push(test);
+        }
+        tx.commit().await?;
+
+        orders
+            .iter()
+            .map(|order| {
+                let tests = tests_by_order.remove(&order.id).unwrap_or_default();
+                self.decrypt_order(order, tests)
+            })
+            .collect()
+    }
+
+    /// Get a lab order
+    pub async fn get_order(
+        &self,
+        patient_id: Uuid,
+        order_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<LabOrderResponse> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        let order = self.find_order(&mut tx, patient_id, order_id).await?;
+        let tests = self.order_tests(&mut tx, &[order_id]).await?;
+        tx.commit().await?;
+
+        self.decrypt_order(&order, tests)
+    }
+
+    /// Update a lab order (status, urgency, lab, clinical information)
+    pub async fn update_order(
+        &self,
+        patient_id: Uuid,
+        order_id: Uuid,
+        req: &UpdateLabOrderRequest,
+        user_id: Uuid,
+    ) -> Result<LabOrderResponse> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        let updated = sqlx::query(
+            r#"
+            UPDATE lab_orders
+            SET status = COALESCE($3, status),
+                urgency = COALESCE($4, urgency),
+                fasting = COALESCE($5, fasting),
+                lab_name = COALESCE($6, lab_name),
+                clinical_info = COALESCE($7, clinical_info),
+                updated_by = $8
+            WHERE id = $1 AND patient_id = $2 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(order_id)
+        .bind(patient_id)
+        .bind(req.status.map(|s| s.as_str()))
+        .bind(req.urgency.map(|u| u.as_str()))
+        .bind(req.fasting)
+        .bind(req.lab_name.as_deref().map(str::trim))
+        .bind(self.encrypt_optional(&req.clinical_info)?)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        if updated == 0 {
+            return Err(AppError::NotFound(format!(
+                "Lab order {} not found",
+                order_id
+            )));
+        }
+
+        let order = self.find_order(&mut tx, patient_id, order_id).await?;
+        let tests = self.order_tests(&mut tx, &[order_id]).await?;
+        tx.commit().await?;
+
+        self.decrypt_order(&order, tests)
+    }
+
+    /// Mark a lab order deleted; its results are kept
+    pub async fn delete_order(
+        &self,
+        patient_id: Uuid,
+        order_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<()> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        let deleted = sqlx::query(
+            r#"
+            UPDATE lab_orders
+            SET deleted_at = NOW(), deleted_by = $3, updated_by = $3
+            WHERE id = $1 AND patient_id = $2 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(order_id)
+        .bind(patient_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        if deleted == 0 {
+            return Err(AppError::NotFound(format!(
+                "Lab order {} not found",
+                order_id
+            )));
+        }
+
+        tx.commit().await?;
+
+        info!(
+            "Lab order {} of patient {} deleted by {}",
+            order_id, patient_id, user_id
+        );
+
+        Ok(())
+    }
+
+    /// Record a lab result
+    ///
+    /// A result answering an order takes the order's visit unless one is
+    /// given. Fails with Validation for an invalid LOINC code or range, or an
+    /// order or visit that is not the patient's, and with Conflict for a
+    /// cancelled order or an anonymized or merged patient.
+    pub async fn create_result(
+        &self,
+        patient_id: Uuid,
+        req: &CreateLabResultRequest,
+        user_id: Uuid,
+    ) -> Result<LabResultResponse> {
+        let code = req.loinc_code.trim();
+        validate_loinc_code(code).map_err(|e| AppError::Validation(e.to_string()))?;
+        check_range(req.reference_low, req.reference_high)?;
+
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        self.check_patient(&mut tx, patient_id).await?;
+
+        let mut visit = None;
+        if let Some(order_id) = req.order_id {
+            let order = self
+                .find_order(&mut tx, patient_id, order_id)
+                .await
+                .map_err(|_| {
+                    AppError::Validation(format!(
+                        "Lab order {} is not an order of this patient",
+                        order_id
+                    ))
+                })?;
+            if order.status == LabOrderStatus::Cancelled.as_str() {
+                return Err(AppError::Conflict(format!(
+                    "Lab order {} has been cancelled",
+                    order_id
+                )));
+            }
+            visit = order.visit_id.zip(order.visit_date);
+        }
+        if let Some(visit_id) = req.visit_id {
+            visit = Some((
+                visit_id,
+                self.visit_date(&mut tx, patient_id, visit_id).await?,
+            ));
+        }
+
+        let value = req.value.trim();
+        let abnormal_flag = req.abnormal_flag.or_else(|| {
+            parse_numeric_value(value)
+                .and_then(|v| AbnormalFlag::from_range(v, req.reference_low, req.reference_high))
+        });
+
+        let result = sqlx::query_as::<_, LabResult>(&format!(
+            r#"
+            INSERT INTO lab_results (
+                patient_id, order_id, visit_id, visit_date, loinc_code, test_name, value, unit,
+                reference_low, reference_high, reference_text, abnormal_flag, collected_at,
+                resulted_at, notes, created_by
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
+            RETURNING {}
+            "#,
+            RESULT_COLUMNS
+        ))
+        .bind(patient_id)
+        .bind(req.order_id)
+        .bind(visit.map(|(id, _)| id))
+        .bind(visit.map(|(_, date)| date))
+        .bind(code)
+        .bind(req.test_name.trim())
+        .bind(self.encrypt(value)?)
+        .bind(req.unit.as_deref().map(str::trim))
+        .bind(req.reference_low)
+        .bind(req.reference_high)
+        .bind(req.reference_text.as_deref().map(str::trim))
+        .bind(abnormal_flag.map(|f| f.as_str()))
+        .bind(req.collected_at)
+        .bind(req.resulted_at)
+        .bind(self.encrypt_optional(&req.notes)?)
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if let Some(order_id) = req.order_id {
+            self.sync_order_status(&mut tx, order_id, user_id).await?;
+        }
+
+        tx.commit().await?;
+
+        info!(
+            "Lab result {} ({}) recorded for patient {} by {}",
+            result.id, code, patient_id, user_id
+        );
+
+        self.decrypt_result(&result)
+    }
+
+    /// List a patient's lab results, most recently collected first
+    pub async fn list_results(
+        &self,
+        patient_id: Uuid,
+        query: &ListLabResultsQuery,
+        user_id: Uuid,
+    ) -> Result<Vec<LabResultResponse>> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        let results = sqlx::query_as::<_, LabResult>(&format!(
+            r#"
+            SELECT {}
+            FROM lab_results
+            WHERE deleted_at IS NULL
+              AND patient_id = $1
+              AND ($2::TEXT IS NULL OR loinc_code = $2)
+              AND ($3::UUID IS NULL OR order_id = $3)
+              AND ($4::UUID IS NULL OR visit_id = $4)
+              AND (NOT $5 OR abnormal_flag <> 'NORMAL')
+              AND ($6::DATE IS NULL OR collected_at >= $6::DATE)
+              AND ($7::DATE IS NULL OR collected_at < $7::DATE + 1)
+            ORDER BY collected_at DESC, loinc_code
+            "#,
+            RESULT_COLUMNS
+        ))
+        .bind(patient_id)
+        .bind(query.loinc_code.as_deref().map(str::trim))
+        .bind(query.order_id)
+        .bind(query.visit_id)
+        .bind(query.abnormal_only)
+        .bind(query.from)
+        .bind(query.to)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        results.iter().map(|r| self.decrypt_result(r)).collect()
+    }
+
+    /// Get a lab result
+    pub async fn get_result(
+        &self,
+        patient_id: Uuid,
+        result_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<LabResultResponse> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        let result = self.find_result(&mut tx, patient_id, result_id).await?;
+        tx.commit().await?;
+
+        self.decrypt_result(&result)
+    }
+
+    /// Update a lab result
+    pub async fn update_result(
+        &self,
+        patient_id: Uuid,
+        result_id: Uuid,
+        req: &UpdateLabResultRequest,
+        user_id: Uuid,
+    ) -> Result<LabResultResponse> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        let existing = self.find_result(&mut tx, patient_id, result_id).await?;
+
+        let reference_low = req.reference_low.or(existing.reference_low);
+        let reference_high = req.reference_high.or(existing.reference_high);
+        check_range(reference_low, reference_high)?;
+
+        let value = req.value.as_deref().map(str::trim);
+        let reflag = value.is_some() || req.reference_low.is_some() || req.reference_high.is_some();
+        let abnormal_flag = match req.abnormal_flag {
+            Some(flag) => Some(flag.as_str().to_string()),
+            None if reflag => {
+                let value = match value {
+                    Some(value) => value.to_string(),
+                    None => self.decrypt_result(&existing)?.value,
+                };
+                parse_numeric_value(&value)
+                    .and_then(|v| AbnormalFlag::from_range(v, reference_low, reference_high))
+                    .map(|flag| flag.as_str().to_string())
+            }
+            None => existing.abnormal_flag.clone(),
+        };
+
+        let encrypted_value = value.map(|v| self.encrypt(v)).transpose()?;
+        sqlx::query(
+            r#"
+            UPDATE lab_results
+            SET value = COALESCE($3, value),
+                unit = COALESCE($4, unit),
+                reference_low = $5,
+                reference_high = $6,
+                reference_text = COALESCE($7, reference_text),
+                abnormal_flag = $8,
+                collected_at = COALESCE($9, collected_at),
+                resulted_at = COALESCE($10, resulted_at),
+                notes = COALESCE($11, notes),
+                updated_by = $12
+            WHERE id = $1 AND patient_id = $2 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(result_id)
+        .bind(patient_id)
+        .bind(encrypted_value)
+        .bind(req.unit.as_deref().map(str::trim))
+        .bind(reference_low)
+        .bind(reference_high)
+        .bind(req.reference_text.as_deref().map(str::trim))
+        .bind(abnormal_flag)
+        .bind(req.collected_at)
+        .bind(req.resulted_at)
+        .bind(self.encrypt_optional(&req.notes)?)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+        let result = self.find_result(&mut tx, patient_id, result_id).await?;
+        tx.commit().await?;
+
+        self.decrypt_result(&result)
+    }
+
+    /// Mark a lab result deleted
+    pub async fn delete_result(
+        &self,
+        patient_id: Uuid,
+        result_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<()> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        let deleted: Option<Option<Uuid>> = sqlx::query_scalar(
+            r#"
+            UPDATE lab_results
+            SET deleted_at = NOW(), deleted_by = $3, updated_by = $3
+            WHERE id = $1 AND patient_id = $2 AND deleted_at IS NULL
+            RETURNING order_id
+            "#,
+        )
+        .bind(result_id)
+        .bind(patient_id)
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(order_id) = deleted else {
+            return Err(AppError::NotFound(format!(
+                "Lab result {} not found",
+                result_id
+            )));
+        };
+
+        if let Some(order_id) = order_id {
+            self.sync_order_status(&mut tx, order_id, user_id).await?;
+        }
+
+        tx.commit().await?;
+
+        info!(
+            "Lab result {} of patient {} deleted by {}",
+            result_id, patient_id, user_id
+        );
+
+        Ok(())
+    }
+
+    /// Results of one analyte of a patient over time, oldest first
+    pub async fn trend(
+        &self,
+        patient_id: Uuid,
+        query: &LabTrendQuery,
+        user_id: Uuid,
+    ) -> Result<LabTrend> {
+        let code = query.loinc_code.trim();
+        validate_loinc_code(code).map_err(|e| AppError::Validation(e.to_string()))?;
+
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        let results = sqlx::query_as::<_, LabResult>(&format!(
+            r#"
+            SELECT {}
+            FROM lab_results
+            WHERE deleted_at IS NULL
+              AND patient_id = $1
+              AND loinc_code = $2
+              AND ($3::DATE IS NULL OR collected_at >= $3::DATE)
+              AND ($4::DATE IS NULL OR collected_at < $4::DATE + 1)
+            ORDER BY collected_at
+            "#,
+            RESULT_COLUMNS
+        ))
+        .bind(patient_id)
+        .bind(code)
+        .bind(query.from)
+        .bind(query.to)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        let test_name = results.last().map(|r| r.test_name.clone());
+        let points = results
+            .iter()
+            .map(|r| self.decrypt_result(r).map(Into::into))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(LabTrend {
+            loinc_code: code.to_string(),
+            test_name,
+            points,
+        })
+    }
+
+    /// Orderable tests, by category and name
+    pub async fn list_catalog(&self, query: &LabCatalogQuery) -> Result<Vec<LabCatalogTest>> {
+        let search = query
+            .search
+            .as_deref()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| format!("%{}%", s));
+
+        Ok(sqlx::query_as::<_, LabCatalogTest>(
+            r#"
+            SELECT loinc_code, test_name, category, specimen, fasting_required
+            FROM lab_test_catalog
+            WHERE is_active
+              AND ($1::TEXT IS NULL OR test_name ILIKE $1 OR loinc_code ILIKE $1)
+              AND ($2::TEXT IS NULL OR category = $2)
+            ORDER BY category, test_name
+            "#,
+        )
+        .bind(search)
+        .bind(query.category.as_deref().map(str::trim))
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    /// Get an order ready to be printed as a requisition, assigning its
+    /// requisition number the first time
+    ///
+    /// Fails with Conflict if the order has been cancelled.
+    pub async fn prepare_requisition(
+        &self,
+        patient_id: Uuid,
+        order_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<LabOrderResponse> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        let order = self.find_order(&mut tx, patient_id, order_id).await?;
+
+        if order.status == LabOrderStatus::Cancelled.as_str() {
+            return Err(AppError::Conflict(format!(
+                "Lab order {} has been cancelled",
+                order_id
+            )));
+        }
+
+        if order.requisition_number.is_none() {
+            sqlx::query(
+                r#"
+                UPDATE lab_orders
+                SET requisition_number = 'LAB-' || TO_CHAR(NOW(), 'YYYY') || '-'
+                        || LPAD(nextval('lab_requisition_number_seq')::TEXT, 6, '0'),
+                    updated_by = $2
+                WHERE id = $1 AND requisition_number IS NULL
+                "#,
+            )
+            .bind(order_id)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        let order = self.find_order(&mut tx, patient_id, order_id).await?;
+        let tests = self.order_tests(&mut tx, &[order_id]).await?;
+        tx.commit().await?;
+
+        self.decrypt_order(&order, tests)
+    }
+
+    /// Link the requisition generated for an order
+    pub async fn link_requisition(
+        &self,
+        patient_id: Uuid,
+        order_id: Uuid,
+        document_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<LabOrderResponse> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        sqlx::query(
+            r#"
+            UPDATE lab_orders
+            SET requisition_document_id = $3, updated_by = $4
+            WHERE id = $1 AND patient_id = $2 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(order_id)
+        .bind(patient_id)
+        .bind(document_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+        let order = self.find_order(&mut tx, patient_id, order_id).await?;
+        let tests = self.order_tests(&mut tx, &[order_id]).await?;
+        tx.commit().await?;
+
+        info!(
+            "Requisition {} generated for lab order {} by {}",
+            document_id, order_id, user_id
+        );
+
+        self.decrypt_order(&order, tests)
+    }
+
+    /// Move an open order to PARTIAL or COMPLETED from its results
+    ///
+    /// Only ORDERED and PARTIAL orders move (back to ORDERED if their last
+    /// result was deleted); completed and cancelled orders are left alone.
+    async fn sync_order_status(
+        &self,
+        tx: &mut PgConnection,
+        order_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<()> {
+        let status: Option<String> = sqlx::query_scalar(
+            r#"
+            UPDATE lab_orders o
+            SET status = CASE
+                    WHEN NOT EXISTS (
+                        SELECT 1 FROM lab_results r
+                        WHERE r.order_id = o.id AND r.deleted_at IS NULL
+                    ) THEN 'ORDERED'
+                    WHEN EXISTS (
+                        SELECT 1 FROM lab_order_tests t
+                        WHERE t.order_id = o.id
+                          AND NOT EXISTS (
+                              SELECT 1 FROM lab_results r
+                              WHERE r.order_id = o.id
+                                AND r.loinc_code = t.loinc_code
+                                AND r.deleted_at IS NULL
+                          )
+                    ) THEN 'PARTIAL'
+                    ELSE 'COMPLETED'
+                END,
+                updated_by = $2
+            WHERE o.id = $1 AND o.status IN ('ORDERED', 'PARTIAL') AND o.deleted_at IS NULL
+            RETURNING o.status
+            "#,
+        )
+        .bind(order_id)
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        if status.as_deref() == Some(LabOrderStatus::Completed.as_str()) {
+            info!("Lab order {} completed: every requested test has a result", order_id);
+        }
+
+        Ok(())
+    }
+
+    async fn find_order(
+        &self,
+        tx: &mut PgConnection,
+        patient_id: Uuid,
+        order_id: Uuid,
+    ) -> Result<LabOrder> {
+        sqlx::query_as::<_, LabOrder>(&format!(
+            r#"
+            SELECT {}
+            FROM lab_orders
+            WHERE id = $1 AND patient_id = $2 AND deleted_at IS NULL
+            "#,
+            ORDER_COLUMNS
+        ))
+        .bind(order_id)
+        .bind(patient_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Lab order {} not found", order_id)))
+    }
+
+    async fn find_result(
+        &self,
+        tx: &mut PgConnection,
+        patient_id: Uuid,
+        result_id: Uuid,
+    ) -> Result<LabResult> {
+        sqlx::query_as::<_, LabResult>(&format!(
+            r#"
+            SELECT {}
+            FROM lab_results
+            WHERE id = $1 AND patient_id = $2 AND deleted_at IS NULL
+            "#,
+            RESULT_COLUMNS
+        ))
+        .bind(result_id)
+        .bind(patient_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Lab result {} not found", result_id)))
+    }
+
+    async fn order_tests(
+        &self,
+        tx: &mut PgConnection,
+        order_ids: &[Uuid],
+    ) -> Result<Vec<LabOrderTest>> {
+        Ok(sqlx::query_as::<_, LabOrderTest>(
+            r#"
+            SELECT order_id, loinc_code, test_name
+            FROM lab_order_tests
+            WHERE order_id = ANY($1)
+            ORDER BY order_id, position
+            "#,
+        )
+        .bind(order_ids)
+        .fetch_all(&mut *tx)
+        .await?)
+    }
+
+    /// The patient must exist and be neither anonymized nor merged
+    async fn check_patient(
+        &self,
+        tx: &mut PgConnection,
+        patient_id: Uuid,
+    ) -> Result<()> {
+        let patient: Option<(
+            Option<chrono::DateTime<chrono::Utc>>,
+            Option<chrono::DateTime<chrono::Utc>>,
+        )> = sqlx::query_as("SELECT anonymized_at, merged_at FROM patients WHERE id = $1")
+            .bind(patient_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        match patient {
+            None => Err(AppError::NotFound(format!(
+                "Patient {} not found",
+                patient_id
+            ))),
+            Some((Some(_), _)) => Err(AppError::Conflict(format!(
+                "Patient {} has been anonymized",
+                patient_id
+            ))),
+            Some((_, Some(_))) => Err(AppError::Conflict(format!(
+                "Patient {} has been merged into another record",
+                patient_id
+            ))),
+            Some(_) => Ok(()),
+        }
+    }
+
+    /// Date of a visit of the patient (the visits partition key)
+    async fn visit_date(
+        &self,
+        tx: &mut PgConnection,
+        patient_id: Uuid,
+        visit_id: Uuid,
+    ) -> Result<NaiveDate> {
+        sqlx::query_scalar("SELECT visit_date FROM visits WHERE id = $1 AND patient_id = $2")
+            .bind(visit_id)
+            .bind(patient_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| {
+                AppError::Validation(format!("Visit {} is not a visit of this patient", visit_id))
+            })
+    }
+
+    fn decrypt_order(
+        &self,
+        order: &LabOrder,
+        tests: Vec<LabOrderTest>,
+    ) -> Result<LabOrderResponse> {
+        order
+            .decrypt(&self.encryption_key, tests)
+            .map_err(|e| AppError::Internal(format!("Failed to decrypt lab order: {}", e)))
+    }
+
+    fn decrypt_result(&self, result: &LabResult) -> Result<LabResultResponse> {
+        result
+            .decrypt(&self.encryption_key)
+            .map_err(|e| AppError::Internal(format!("Failed to decrypt lab result: {}", e)))
+    }
+
+    fn encrypt(&self, value: &str) -> Result<String> {
+        self.encryption_key
+            .encrypt(value)
+            .map_err(|e| AppError::Internal(format!("Failed to encrypt lab data: {}", e)))
+    }
+
+    fn encrypt_optional(&self, value: &Option<String>) -> Result<Option<String>> {
+        value.as_deref().map(|v| self.encrypt(v)).transpose()
+    }
+}
+
+fn check_range(low: Option<f64>, high: Option<f64>) -> Result<()> {
+    if let (Some(low), Some(high)) = (low, high) {
+        if low > high {
+            return Err(AppError::Validation(
+                "reference_low cannot be above reference_high".to_string(),
+            ));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,593 @@
+/*!
+ * Medication Favorite Service
+ *
+ * Per-prescriber favorite medications and prescribing usage:
+ * - favorites CRUD (each user only sees and edits their own)
+ * - `quick_pick`: favorites, then the medications the user prescribes most
+ * - `rank_search_results`: puts favorites first in medication search and
+ *   ranks the rest by how often the user prescribed them
+ *
+ * Usage is counted per prescriber in medication_usage, since prescriptions
+ * store the medication name encrypted. `record_medication_usage` bumps the
+ * counter when a prescription is created; the first time a user has no
+ * counters yet, they are learned from all of the user's prescriptions.
+ */
+
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    db::rls::apply_rls_context,
+    models::{
+        medication_favorite::{medication_key, MAX_MEDICATION_FAVORITES},
+        CreateMedicationFavoriteRequest, FrequentMedication, MedicationFavorite, MedicationForm,
+        QuickPickResponse, UpdateMedicationFavoriteRequest,
+    },
+    services::prescription_service::MedicationSearchResult,
+    utils::{encryption::EncryptionKey, AppError, Result},
+};
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+const FAVORITE_COLUMNS: &str = r#"
+    id, user_id, medication_name, generic_name, form, route, dosage, frequency, duration,
+    quantity, refills, instructions, sort_order, created_at, updated_at
+"#;
+
+/// Medication the user prescribed before, matching a search
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct MedicationUsage {
+    pub medication_key: String,
+    pub medication_name: String,
+    pub prescription_count: i32,
+}
+
+/// Count a new prescription of `medication_name` by `user_id`
+///
+/// Runs in the prescription's transaction (RLS context already set). If the
+/// user has no counters yet they are learned from their prescriptions,
+/// including this one.
+pub async fn record_medication_usage(
+    tx: &mut Transaction<'_, Postgres>,
+    encryption_key: &EncryptionKey,
+    user_id: Uuid,
+    medication_name: &str,
+) -> Result<()> {
+    if learn_usage_from_history(tx, encryption_key, user_id).await? {
+        return Ok(());
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO medication_usage (user_id, medication_key, medication_name, prescription_count)
+        VALUES ($1, $2, $3, 1)
+        ON CONFLICT (user_id, medication_key) DO UPDATE SET
+            medication_name = EXCLUDED.medication_name,
+            prescription_count = medication_usage.prescription_count + 1,
+            last_prescribed_at = NOW()
+        "#,
+    )
+    .bind(user_id)
+    .bind(medication_key(medication_name))
+    .bind(medication_name.trim())
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+/// Build the user's counters from their prescriptions, if they have none
+///
+/// Returns whether anything was learned.
+async fn learn_usage_from_history(
+    tx: &mut Transaction<'_, Postgres>,
+    encryption_key: &EncryptionKey,
+    user_id: Uuid,
+) -> Result<bool> {
+    let has_usage: bool =
+        sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM medication_usage WHERE user_id = $1)")
+            .bind(user_id)
+            .fetch_one(&mut **tx)
+            .await?;
+    if has_usage {
+        return Ok(false);
+    }
+
+    let prescriptions: Vec<(String, DateTime<Utc>)> = sqlx::query_as(
+        "SELECT medication_name, created_at FROM prescriptions WHERE provider_id = $1 ORDER BY created_at",
+    )
+    .bind(user_id)
+    .fetch_all(&mut **tx)
+    .await?;
+    if prescriptions.is_empty() {
+        return Ok(false);
+    }
+
+    let mut usage: HashMap<String, (String, i32, DateTime<Utc>)> = HashMap::new();
+    for (encrypted_name, created_at) in prescriptions {
+        let name = match encryption_key.decrypt(&encrypted_name) {
+            Ok(name) => name.trim().to_string(),
+            Err(e) => {
+                warn!(
+                    "Skipping a prescription of user {} in usage: {}",
+                    user_id, e
+                );
+                continue;
+            }
+        };
+        // Ordered by date: the last name and date win
+        let entry = usage
+            .entry(medication_key(&name))
+            .or_insert_with(|| (name.clone(), 0, created_at));
+        entry.0 = name;
+        entry.1 += 1;
+        entry.2 = created_at;
+    }
+
+    let mut keys = Vec::with_capacity(usage.len());
+    let mut names = Vec::with_capacity(usage.len());
+    let mut counts = Vec::with_capacity(usage.len());
+    let mut dates = Vec::with_capacity(usage.len());
+    for (key, (name, count, last)) in usage {
+        keys.push(key);
+        names.push(name);
+        counts.push(count);
+        dates.push(last);
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO medication_usage (
+            user_id, medication_key, medication_name, prescription_count, last_prescribed_at
+        )
+        SELECT $1, v.medication_key, v.medication_name, v.prescription_count, v.last_prescribed_at
+        FROM UNNEST($2::VARCHAR[], $3::VARCHAR[], $4::INT[], $5::TIMESTAMPTZ[])
+            AS v (medication_key, medication_name, prescription_count, last_prescribed_at)
+        ON CONFLICT (user_id, medication_key) DO NOTHING
+        "#,
+    )
+    .bind(user_id)
+    .bind(&keys)
+    .bind(&names)
+    .bind(&counts)
+    .bind(&dates)
+    .execute(&mut **tx)
+    .await?;
+
+    info!(
+        "Learned usage of {} medications from the history of user {}",
+        keys.len(),
+        user_id
+    );
+    Ok(true)
+}
+
+/// Put favorites first in catalog search results and rank the rest by usage
+///
+/// `favorites` and `usage` are the user's entries matching the search.
+/// Favorites keep their order; the other results are ordered by how often
+/// the user prescribed them, keeping catalog relevance between equals.
+/// Medications the user prescribed that the catalog did not return are
+/// included too.
+pub fn rank_medication_results(
+    catalog: Vec<MedicationSearchResult>,
+    favorites: &[MedicationFavorite],
+    usage: &[MedicationUsage],
+    limit: usize,
+) -> Vec<MedicationSearchResult> {
+    let counts: HashMap<&str, i32> = usage
+        .iter()
+        .map(|u| (u.medication_key.as_str(), u.prescription_count))
+        .collect();
+    let mut seen = HashSet::new();
+    let mut results = Vec::new();
+
+    for favorite in favorites {
+        let key = medication_key(&favorite.medication_name);
+        if !seen.insert(key.clone()) {
+            continue;
+        }
+        let listed = catalog.iter().find(|m| medication_key(&m.name) == key);
+
+        results.push(MedicationSearchResult {
+            name: favorite.medication_name.clone(),
+            generic_name: favorite
+                .generic_name
+                .clone()
+                .or_else(|| listed.and_then(|m| m.generic_name.clone())),
+            form: favorite
+                .form
+                .as_deref()
+                .and_then(parse_form)
+                .or_else(|| listed.and_then(|m| m.form)),
+            common_dosages: listed
+                .map(|m| m.common_dosages.clone())
+                .unwrap_or_else(|| favorite.dosage.iter().cloned().collect()),
+            is_favorite: true,
+            usage_count: counts.get(key.as_str()).copied().unwrap_or(0),
+            defaults: Some(favorite.defaults()),
+        });
+    }
+
+    let mut others = Vec::new();
+    for mut medication in catalog {
+        let key = medication_key(&medication.name);
+        if seen.insert(key.clone()) {
+            medication.usage_count = counts.get(key.as_str()).copied().unwrap_or(0);
+            others.push(medication);
+        }
+    }
+    for used in usage {
+        if seen.insert(used.medication_key.clone()) {
+            others.push(MedicationSearchResult {
+                name: used.medication_name.clone(),
+                usage_count: used.prescription_count,
+                ..Default::default()
+            });
+        }
+    }
+    // Stable: catalog relevance is kept between equal counts
+    others.sort_by_key(|m| Reverse(m.usage_count));
+
+    results.extend(others);
+    results.truncate(limit);
+    results
+}
+
+/// Parse a stored form (TABLET, CAPSULE, ...)
+fn parse_form(form: &str) -> Option<MedicationForm> {
+    serde_json::from_value(serde_json::Value::String(form.to_uppercase())).ok()
+}
+
+/// Medication favorite service
+pub struct MedicationFavoriteService {
+    pool: PgPool,
+    encryption_key: EncryptionKey,
+}
+
+impl MedicationFavoriteService {
+    /// Create a new medication favorite service
+    pub fn new(pool: PgPool, encryption_key: EncryptionKey) -> Self {
+        Self {
+            pool,
+            encryption_key,
+        }
+    }
+
+    /// The user's favorites in quick-pick order
+    pub async fn list_favorites(&self, user_id: Uuid) -> Result<Vec<MedicationFavorite>> {
+        let favorites = sqlx::query_as::<_, MedicationFavorite>(&format!(
+            r#"
+            SELECT {}
+            FROM medication_favorites
+            WHERE user_id = $1
+            ORDER BY sort_order, LOWER(medication_name)
+            "#,
+            FAVORITE_COLUMNS
+        ))
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(favorites)
+    }
+
+    /// Add a favorite
+    ///
+    /// Fails with Conflict if the medication is already a favorite and with
+    /// Validation once the user has the maximum number of favorites.
+    pub async fn create_favorite(
+        &self,
+        user_id: Uuid,
+        req: CreateMedicationFavoriteRequest,
+    ) -> Result<MedicationFavorite> {
+        let mut tx = self.pool.begin().await?;
+
+        let (count, next_sort_order): (i64, i32) = sqlx::query_as(
+            "SELECT COUNT(*), COALESCE(MAX(sort_order) + 1, 0) FROM medication_favorites WHERE user_id = $1",
+        )
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+        if count >= MAX_MEDICATION_FAVORITES {
+            return Err(AppError::Validation(format!(
+                "At most {} favorite medications",
+                MAX_MEDICATION_FAVORITES
+            )));
+        }
+
+        let medication_name = req.medication_name.trim().to_string();
+        let inserted = sqlx::query_as::<_, MedicationFavorite>(&format!(
+            r#"
+            INSERT INTO medication_favorites (
+                user_id, medication_name, generic_name, form, route, dosage, frequency,
+                duration, quantity, refills, instructions, sort_order
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+            RETURNING {}
+            "#,
+            FAVORITE_COLUMNS
+        ))
+        .bind(user_id)
+        .bind(&medication_name)
+        .bind(&req.generic_name)
+        .bind(req.form.map(|f| format!("{:?}", f).to_uppercase()))
+        .bind(req.route.map(|r| format!("{:?}", r).to_uppercase()))
+        .bind(&req.dosage)
+        .bind(&req.frequency)
+        .bind(&req.duration)
+        .bind(req.quantity)
+        .bind(req.refills)
+        .bind(&req.instructions)
+        .bind(req.sort_order.unwrap_or(next_sort_order))
+        .fetch_one(&mut *tx)
+        .await;
+
+        let favorite = match inserted {
+            Ok(favorite) => favorite,
+            Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+                return Err(AppError::Conflict(format!(
+                    "{} is already a favorite",
+                    medication_name
+                )));
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        tx.commit().await?;
+
+        info!("Favorite medication {} added by {}", favorite.id, user_id);
+        Ok(favorite)
+    }
+
+    /// Change a favorite's default dosing or position
+    pub async fn update_favorite(
+        &self,
+        user_id: Uuid,
+        favorite_id: Uuid,
+        req: UpdateMedicationFavoriteRequest,
+    ) -> Result<MedicationFavorite> {
+        sqlx::query_as::<_, MedicationFavorite>(&format!(
+            r#"
+            UPDATE medication_favorites
+            SET generic_name = COALESCE($3, generic_name),
+                form = COALESCE($4, form),
+                route = COALESCE($5, route),
+                dosage = COALESCE($6, dosage),
+                frequency = COALESCE($7, frequency),
+                duration = COALESCE($8, duration),
+                quantity = COALESCE($9, quantity),
+                refills = COALESCE($10, refills),
+                instructions = COALESCE($11, instructions),
+                sort_order = COALESCE($12, sort_order)
+            WHERE id = $1 AND user_id = $2
+            RETURNING {}
+            "#,
+            FAVORITE_COLUMNS
+        ))
+        .bind(favorite_id)
+        .bind(user_id)
+        .bind(&req.generic_name)
+        .bind(req.form.map(|f| format!("{:?}", f).to_uppercase()))
+        .bind(req.route.map(|r| format!("{:?}", r).to_uppercase()))
+        .bind(&req.dosage)
+        .bind(&req.frequency)
+        .bind(&req.duration)
+        .bind(req.quantity)
+        .bind(req.refills)
+        .bind(&req.instructions)
+        .bind(req.sort_order)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Favorite medication not found".to_string()))
+    }
+
+    /// Remove a favorite
+    pub async fn delete_favorite(&self, user_id: Uuid, favorite_id: Uuid) -> Result<()> {
+        let result = sqlx::query("DELETE FROM medication_favorites WHERE id = $1 AND user_id = $2")
+            .bind(favorite_id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound(
+                "Favorite medication not found".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Favorites, then the `limit` medications the user prescribes most
+    /// that are not favorites
+    pub async fn quick_pick(
+        &self,
+        user_id: Uuid,
+        role: &str,
+        limit: i64,
+    ) -> Result<QuickPickResponse> {
+        self.ensure_usage_learned(user_id, role).await?;
+
+        let favorites = self.list_favorites(user_id).await?;
+        let favorite_keys: Vec<String> = favorites
+            .iter()
+            .map(|f| medication_key(&f.medication_name))
+            .collect();
+
+        let frequent = sqlx::query_as::<_, FrequentMedication>(
+            r#"
+            SELECT medication_name, prescription_count, last_prescribed_at
+            FROM medication_usage
+            WHERE user_id = $1
+              AND medication_key <> ALL($2)
+            ORDER BY prescription_count DESC, last_prescribed_at DESC
+            LIMIT $3
+            "#,
+        )
+        .bind(user_id)
+        .bind(&favorite_keys)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(QuickPickResponse {
+            favorites,
+            frequent,
+        })
+    }
+
+    /// Rank catalog search results for the user (see
+    /// `rank_medication_results`)
+    pub async fn rank_search_results(
+        &self,
+        query: &str,
+        catalog: Vec<MedicationSearchResult>,
+        limit: i64,
+        user_id: Uuid,
+        role: &str,
+    ) -> Result<Vec<MedicationSearchResult>> {
+        self.ensure_usage_learned(user_id, role).await?;
+
+        let pattern = format!("%{}%", medication_key(query));
+
+        let favorites = sqlx::query_as::<_, MedicationFavorite>(&format!(
+            r#"
+            SELECT {}
+            FROM medication_favorites
+            WHERE user_id = $1
+              AND (LOWER(medication_name) LIKE $2 OR LOWER(generic_name) LIKE $2)
+            ORDER BY sort_order, LOWER(medication_name)
+            "#,
+            FAVORITE_COLUMNS
+        ))
+        .bind(user_id)
+        .bind(&pattern)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let usage = sqlx::query_as::<_, MedicationUsage>(
+            r#"
+            SELECT medication_key, medication_name, prescription_count
+            FROM medication_usage
+            WHERE user_id = $1
+              AND medication_key LIKE $2
+            ORDER BY prescription_count DESC
+            LIMIT $3
+            "#,
+        )
+        .bind(user_id)
+        .bind(&pattern)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rank_medication_results(
+            catalog,
+            &favorites,
+            &usage,
+            limit.max(0) as usize,
+        ))
+    }
+
+    /// Learn the user's counters from their prescriptions if they have none
+    async fn ensure_usage_learned(&self, user_id: Uuid, role: &str) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        apply_rls_context(&mut tx, user_id, role)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        learn_usage_from_history(&mut tx, &self.encryption_key, user_id).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn favorite(name: &str, dosage: &str) -> MedicationFavorite {
+        MedicationFavorite {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            medication_name: name.to_string(),
+            generic_name: None,
+            form: Some("TABLET".to_string()),
+            route: Some("ORAL".to_string()),
+            dosage: Some(dosage.to_string()),
+            frequency: Some("1 volta al giorno".to_string()),
+            duration: None,
+            quantity: Some(30),
+            refills: None,
+            instructions: None,
+            sort_order: 0,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn listed(name: &str) -> MedicationSearchResult {
+        MedicationSearchResult {
+            name: name.to_string(),
+            common_dosages: vec!["500mg".to_string()],
+            ..Default::default()
+        }
+    }
+
+    fn used(name: &str, count: i32) -> MedicationUsage {
+        MedicationUsage {
+            medication_key: medication_key(name),
+            medication_name: name.to_string(),
+            prescription_count: count,
+        }
+    }
+
+    #[test]
+    fn test_rank_medication_results() {
+        let catalog = vec![
+            listed("Metformina"),
+            listed("Metoprololo"),
+            listed("Metotrexato"),
+        ];
+        let favorites = vec![favorite("Metotrexato", "2.5 mg")];
+        let usage = vec![
+            used("metoprololo", 7),
+            used("Metotrexato", 3),
+            used("Metadone", 2),
+        ];
+
+        let results = rank_medication_results(catalog, &favorites, &usage, 10);
+        let names: Vec<&str> = results.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["Metotrexato", "Metoprololo", "Metadone", "Metformina"]
+        );
+
+        let top = &results[0];
+        assert!(top.is_favorite);
+        assert_eq!(top.usage_count, 3);
+        assert_eq!(top.form, Some(MedicationForm::Tablet));
+        assert_eq!(top.common_dosages, vec!["500mg".to_string()]);
+        assert_eq!(
+            top.defaults.as_ref().and_then(|d| d.dosage.as_deref()),
+            Some("2.5 mg")
+        );
+
+        assert!(!results[1].is_favorite);
+        assert_eq!(results[1].usage_count, 7);
+        assert_eq!(results[3].usage_count, 0);
+    }
+
+    #[test]
+    fn test_rank_medication_results_limit() {
+        let catalog = vec![listed("A"), listed("B"), listed("C")];
+        let favorites = vec![favorite("Z", "1 mg")];
+
+        let results = rank_medication_results(catalog, &favorites, &[], 2);
+        let names: Vec<&str> = results.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["Z", "A"]);
+        // Not in the catalog: the favorite's own dosage is offered
+        assert_eq!(results[0].common_dosages, vec!["1 mg".to_string()]);
+    }
+}
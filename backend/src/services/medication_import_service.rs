@@ -0,0 +1,1116 @@
+/*!
+ * Medication Import Service
+ *
+ * Imports the AIFA (Italian Medicines Agency) Class A list into the
+ * `medications` reference table used by medication search and prescribing:
+ * - ATC codes (optional CSV, `codice_atc;descrizione`) are upserted first
+ * - Packages are grouped into one medication per commercial name, keeping
+ *   every AIC code, package description and public price in `packages`
+ * - Medications get the ATC code whose level-5 description is their active
+ *   ingredient
+ * - AIFA medications no longer in the list are deactivated
+ *
+ * Each sync is diffed against the current AIFA rows (added, changed,
+ * withdrawn) and recorded in `medication_sync_runs`; a dry run only reports
+ * the diff. Syncs run from `POST /api/v1/medication-sync/run`, the
+ * `import-medications` CLI, or on a schedule (`medication_sync_interval_days`)
+ * from the URLs in the `aifa_class_a_url` and `aifa_atc_url` settings.
+ */
+
+use crate::db::rls::{apply_rls_context, SYSTEM_ROLE, SYSTEM_USER_ID};
+use crate::models::{
+    AuditAction, AuditLog, CreateAuditLog, EntityType, ListMedicationSyncRunsQuery,
+    MedicationChange, MedicationDiffEntry, MedicationFieldChange, MedicationPackage,
+    MedicationSyncDiff, MedicationSyncRun, MedicationSyncStatus,
+};
+use crate::services::{CacheNamespace, ReferenceCache, SettingsService};
+use crate::utils::{AppError, Result};
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, PgPool, Postgres, Transaction};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::sleep;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// Default days between scheduled syncs
+const DEFAULT_INTERVAL_DAYS: i64 = 30;
+
+/// Entries kept per diff list
+const DIFF_LIMIT: usize = 500;
+
+/// Download timeout for the AIFA files
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(120);
+
+const RUN_COLUMNS: &str = r#"
+    id, source, dry_run, status, records_parsed, atc_codes_imported, added_count,
+    updated_count, unchanged_count, deactivated_count, diff, error_message,
+    triggered_by, started_at, completed_at
+"#;
+
+/// Package record of the AIFA Class A CSV
+#[derive(Debug, Clone, PartialEq)]
+pub struct AifaMedication {
+    /// Active ingredient (Principio Attivo)
+    pub active_ingredient: String,
+    /// Group description (Descrizione Gruppo)
+    pub group_description: String,
+    /// Commercial name (before the `*` of Denominazione e Confezione)
+    pub commercial_name: String,
+    /// Package description (after the `*`)
+    pub package_description: String,
+    /// Public price
+    pub price: Option<f64>,
+    /// AIC holder company
+    pub manufacturer: String,
+    /// AIC code (authorization number)
+    pub aic_code: String,
+    /// Equivalence group code
+    pub equivalence_group: Option<String>,
+    pub is_generic: bool,
+}
+
+/// ATC code record
+#[derive(Debug, Clone, PartialEq)]
+pub struct AtcCode {
+    pub code: String,
+    pub description: String,
+}
+
+/// Parse the AIFA Class A CSV
+///
+/// Semicolon-separated, with a header line:
+/// `Principio Attivo;Descrizione Gruppo;Denominazione e Confezione;Prezzo;Titolare AIC;AIC;Codice Gruppo Equivalenza;...`
+/// Lines with missing fields or no AIC code are skipped.
+pub fn parse_aifa_class_a_csv(content: &str) -> Vec<AifaMedication> {
+    let mut medications = Vec::new();
+
+    for (line_num, line) in content.lines().enumerate().skip(1) {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split(';').collect();
+        if fields.len() < 7 || fields[5].trim().is_empty() {
+            warn!("AIFA line {} has insufficient fields", line_num + 1);
+            continue;
+        }
+
+        let name_and_package = fields[2].trim();
+        let (commercial_name, package_description) = match name_and_package.split_once('*') {
+            Some((name, package)) => (name.trim().to_string(), package.trim().to_string()),
+            None => (name_and_package.to_string(), String::new()),
+        };
+        if commercial_name.is_empty() {
+            continue;
+        }
+
+        let equivalence_group = Some(fields[6].trim())
+            .filter(|g| !g.is_empty())
+            .map(str::to_string);
+
+        medications.push(AifaMedication {
+            active_ingredient: fields[0].trim().to_string(),
+            group_description: fields[1].trim().to_string(),
+            commercial_name,
+            package_description,
+            price: fields[3].trim().replace(',', ".").parse::<f64>().ok(),
+            manufacturer: fields[4].trim().to_string(),
+            aic_code: fields[5].trim().to_string(),
+            equivalence_group,
+            is_generic: fields.get(7).map(|s| s.trim() == "X").unwrap_or(false),
+        });
+    }
+
+    medications
+}
+
+/// Parse the ATC codes CSV (`codice_atc;descrizione`, with a header line)
+pub fn parse_atc_csv(content: &str) -> Vec<AtcCode> {
+    content
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let (code, description) = line.split_once(';')?;
+            let code = code.trim().to_uppercase();
+            let description = description.split(';').next().unwrap_or("").trim();
+            (!code.is_empty() && !description.is_empty()).then(|| AtcCode {
+                code,
+                description: description.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// AIFA files are published in UTF-8 or Windows-1252
+pub fn decode_csv(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => bytes.iter().map(|&b| b as char).collect(),
+    }
+}
+
+/// Extract dosage strength from group description
+/// e.g., "ACARBOSIO 100MG 40 UNITA' USO ORALE" -> "100MG"
+fn extract_dosage_strength(group_description: &str) -> Option<String> {
+    let re = regex::Regex::new(
+        r"(\d+[,.]?\d*\s*(MG|MCG|G|ML|UI|UNITA)[^A-Z]*(?:/\d+[,.]?\d*\s*(MG|MCG|G|ML))?)",
+    )
+    .ok()?;
+    re.find(group_description)
+        .map(|m| m.as_str().trim().to_string())
+}
+
+/// Extract pharmaceutical form from package description
+fn extract_form(package_description: &str) -> Option<&'static str> {
+    let desc = package_description.to_lowercase();
+    let has = |terms: &[&str]| terms.iter().any(|t| desc.contains(t));
+
+    if has(&["cpr", "compresse"]) {
+        Some("Tablet")
+    } else if has(&["cps", "capsule"]) {
+        Some("Capsule")
+    } else if has(&["fl", "fiala", "fiale", "penna", "siringa"]) {
+        Some("Injection")
+    } else if has(&["supp"]) {
+        Some("Suppository")
+    } else if has(&["crema", "unguento", "gel"]) {
+        Some("Topical")
+    } else if has(&["sciroppo", "soluzione", "sospensione"]) {
+        Some("Liquid")
+    } else if has(&["gocce"]) {
+        Some("Drops")
+    } else if has(&["inhaler", "spray", "aerosol"]) {
+        Some("Inhaler")
+    } else if has(&["cerotto", "patch"]) {
+        Some("Patch")
+    } else {
+        None
+    }
+}
+
+/// Extract route of administration from group description
+fn extract_route(group_description: &str) -> Option<&'static str> {
+    let desc = group_description.to_lowercase();
+    let has = |terms: &[&str]| terms.iter().any(|t| desc.contains(t));
+
+    if has(&["uso orale", "per os"]) {
+        Some("ORAL")
+    } else if has(&[
+        "uso parenterale",
+        "iniettabile",
+        "sottocutaneo",
+        "intramuscolo",
+    ]) {
+        Some("INJECTION")
+    } else if has(&["uso topico", "uso cutaneo"]) {
+        Some("TOPICAL")
+    } else if has(&["uso inalatorio", "inalazione"]) {
+        Some("INHALATION")
+    } else if has(&["uso rettale"]) {
+        Some("RECTAL")
+    } else if has(&["uso nasale"]) {
+        Some("NASAL")
+    } else if has(&["uso oftalmico", "collirio"]) {
+        Some("OPHTHALMIC")
+    } else if has(&["transdermico"]) {
+        Some("TRANSDERMAL")
+    } else if has(&["sublinguale"]) {
+        Some("SUBLINGUAL")
+    } else {
+        None
+    }
+}
+
+/// Hierarchy level of an ATC code and its parent
+fn atc_level(code: &str) -> (i32, Option<String>) {
+    match code.len() {
+        1 => (1, None),
+        3 => (2, Some(code[..1].to_string())),
+        4 => (3, Some(code[..3].to_string())),
+        5 => (4, Some(code[..4].to_string())),
+        _ => (5, code.get(..5).map(str::to_string)),
+    }
+}
+
+/// Medication row built from the AIFA packages of one commercial name
+#[derive(Debug, Clone, PartialEq)]
+struct AifaMedicationRow {
+    name: String,
+    generic_name: String,
+    /// Lowest AIC code of the packages
+    aic_code: String,
+    atc_code: Option<String>,
+    form: Option<&'static str>,
+    dosage_strength: Option<String>,
+    route: Option<&'static str>,
+    package_description: String,
+    manufacturer: String,
+    is_generic: bool,
+    common_dosages: Vec<String>,
+    packages: Vec<MedicationPackage>,
+    public_price: Option<f64>,
+}
+
+/// Group packages by commercial name, in name order
+///
+/// `atc_by_ingredient` maps upper-case level-5 ATC descriptions to codes.
+fn build_rows(
+    medications: &[AifaMedication],
+    atc_by_ingredient: &HashMap<String, String>,
+) -> Vec<AifaMedicationRow> {
+    let mut by_name: BTreeMap<&str, Vec<&AifaMedication>> = BTreeMap::new();
+    for med in medications {
+        by_name
+            .entry(med.commercial_name.as_str())
+            .or_default()
+            .push(med);
+    }
+
+    by_name
+        .into_iter()
+        .map(|(name, mut entries)| {
+            entries.sort_by(|a, b| a.aic_code.cmp(&b.aic_code));
+            entries.dedup_by(|a, b| a.aic_code == b.aic_code);
+            let first = entries[0];
+
+            let common_dosages: Vec<String> = entries
+                .iter()
+                .filter_map(|m| extract_dosage_strength(&m.group_description))
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .take(5)
+                .collect();
+
+            let packages: Vec<MedicationPackage> = entries
+                .iter()
+                .map(|m| MedicationPackage {
+                    aic_code: m.aic_code.clone(),
+                    description: m.package_description.clone(),
+                    price: m.price,
+                    equivalence_group: m.equivalence_group.clone(),
+                })
+                .collect();
+
+            let public_price = packages
+                .iter()
+                .filter_map(|p| p.price)
+                .min_by(|a, b| a.total_cmp(b));
+
+            AifaMedicationRow {
+                name: name.to_string(),
+                generic_name: first.active_ingredient.clone(),
+                aic_code: first.aic_code.clone(),
+                atc_code: atc_by_ingredient
+                    .get(&first.active_ingredient.to_uppercase())
+                    .cloned(),
+                form: extract_form(&first.package_description),
+                dosage_strength: extract_dosage_strength(&first.group_description),
+                route: extract_route(&first.group_description),
+                package_description: first.package_description.clone(),
+                manufacturer: first.manufacturer.clone(),
+                is_generic: first.is_generic,
+                common_dosages,
+                packages,
+                public_price,
+            }
+        })
+        .collect()
+}
+
+/// Current AIFA medication row
+#[derive(Debug, Clone, FromRow)]
+struct ExistingMedication {
+    id: Uuid,
+    name: String,
+    generic_name: Option<String>,
+    aic_code: Option<String>,
+    atc_code: Option<String>,
+    packages: serde_json::Value,
+    is_active: Option<bool>,
+}
+
+fn format_price(price: Option<f64>) -> Option<String> {
+    price.map(|p| format!("{:.2}", p))
+}
+
+/// Fields of a medication that changed since the last sync
+///
+/// An ATC code that could not be derived this time does not count as a
+/// change (and is not cleared).
+fn diff_medication(
+    existing: &ExistingMedication,
+    row: &AifaMedicationRow,
+) -> Vec<MedicationFieldChange> {
+    let mut changes = Vec::new();
+    let mut change =
+        |field: &str, aic_code: Option<&str>, old: Option<String>, new: Option<String>| {
+            changes.push(MedicationFieldChange {
+                field: field.to_string(),
+                aic_code: aic_code.map(str::to_string),
+                old,
+                new,
+            })
+        };
+
+    if existing.is_active == Some(false) {
+        change(
+            "is_active",
+            None,
+            Some("false".to_string()),
+            Some("true".to_string()),
+        );
+    }
+    if existing.generic_name.as_deref() != Some(row.generic_name.as_str()) {
+        change(
+            "generic_name",
+            None,
+            existing.generic_name.clone(),
+            Some(row.generic_name.clone()),
+        );
+    }
+    if existing.aic_code.as_deref() != Some(row.aic_code.as_str()) {
+        change(
+            "aic_code",
+            None,
+            existing.aic_code.clone(),
+            Some(row.aic_code.clone()),
+        );
+    }
+    if row.atc_code.is_some() && existing.atc_code != row.atc_code {
+        change(
+            "atc_code",
+            None,
+            existing.atc_code.clone(),
+            row.atc_code.clone(),
+        );
+    }
+
+    let old_packages: Vec<MedicationPackage> =
+        serde_json::from_value(existing.packages.clone()).unwrap_or_default();
+    let old_by_aic: HashMap<&str, &MedicationPackage> = old_packages
+        .iter()
+        .map(|p| (p.aic_code.as_str(), p))
+        .collect();
+    let new_aics: HashSet<&str> = row.packages.iter().map(|p| p.aic_code.as_str()).collect();
+
+    for package in &row.packages {
+        match old_by_aic.get(package.aic_code.as_str()) {
+            None => change(
+                "package_added",
+                Some(package.aic_code.as_str()),
+                None,
+                Some(package.description.clone()),
+            ),
+            Some(old) if old.price != package.price => change(
+                "price",
+                Some(package.aic_code.as_str()),
+                format_price(old.price),
+                format_price(package.price),
+            ),
+            Some(_) => {}
+        }
+    }
+    for package in &old_packages {
+        if !new_aics.contains(package.aic_code.as_str()) {
+            change(
+                "package_removed",
+                Some(package.aic_code.as_str()),
+                Some(package.description.clone()),
+                None,
+            );
+        }
+    }
+
+    changes
+}
+
+/// Counts and diff of one sync
+#[derive(Debug, Default)]
+struct SyncOutcome {
+    records_parsed: i32,
+    atc_codes_imported: i32,
+    added: i32,
+    updated: i32,
+    unchanged: i32,
+    deactivated: i32,
+    diff: MedicationSyncDiff,
+}
+
+/// Medication import service
+pub struct MedicationImportService {
+    pool: PgPool,
+    cache: Option<ReferenceCache>,
+}
+
+impl MedicationImportService {
+    /// Create a new medication import service
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool, cache: None }
+    }
+
+    /// Invalidate cached medication searches after an import
+    pub fn with_cache(mut self, cache: ReferenceCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Download the AIFA files from the configured URLs and import them
+    pub async fn sync_from_settings(
+        &self,
+        settings_service: &SettingsService,
+        dry_run: bool,
+        triggered_by: Option<Uuid>,
+    ) -> Result<MedicationSyncRun> {
+        let class_a_url = settings_service
+            .get_setting_value::<String>("aifa_class_a_url")
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?
+            .filter(|url| !url.trim().is_empty())
+            .ok_or_else(|| {
+                AppError::BadRequest("The aifa_class_a_url setting is not configured".to_string())
+            })?;
+        let atc_url = settings_service
+            .get_setting_value::<String>("aifa_atc_url")
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?
+            .filter(|url| !url.trim().is_empty());
+
+        let class_a_csv = match download(&class_a_url).await {
+            Ok(csv) => csv,
+            Err(e) => {
+                return self
+                    .record_failure(dry_run, triggered_by, Utc::now(), &e.to_string())
+                    .await
+            }
+        };
+        let atc_csv = match atc_url {
+            Some(url) => match download(&url).await {
+                Ok(csv) => Some(csv),
+                Err(e) => {
+                    return self
+                        .record_failure(dry_run, triggered_by, Utc::now(), &e.to_string())
+                        .await
+                }
+            },
+            None => None,
+        };
+
+        self.import(&class_a_csv, atc_csv.as_deref(), dry_run, triggered_by)
+            .await
+    }
+
+    /// Import the AIFA Class A list (and optionally the ATC codes)
+    ///
+    /// The run is recorded whether it completes or fails; a failed run
+    /// changes nothing.
+    pub async fn import(
+        &self,
+        class_a_csv: &str,
+        atc_csv: Option<&str>,
+        dry_run: bool,
+        triggered_by: Option<Uuid>,
+    ) -> Result<MedicationSyncRun> {
+        let started_at = Utc::now();
+
+        match self
+            .apply_import(class_a_csv, atc_csv, dry_run, triggered_by)
+            .await
+        {
+            Ok(outcome) => {
+                let run = self
+                    .record_run(
+                        dry_run,
+                        MedicationSyncStatus::Completed,
+                        &outcome,
+                        None,
+                        triggered_by,
+                        started_at,
+                    )
+                    .await?;
+
+                info!(
+                    "Medication sync {}{}: {} added, {} updated, {} unchanged, {} deactivated",
+                    run.id,
+                    if dry_run { " (dry run)" } else { "" },
+                    run.added_count,
+                    run.updated_count,
+                    run.unchanged_count,
+                    run.deactivated_count
+                );
+
+                if !dry_run {
+                    if let Some(cache) = &self.cache {
+                        cache.invalidate_all(CacheNamespace::Medications).await;
+                    }
+                }
+
+                Ok(run)
+            }
+            Err(e) => {
+                error!("Medication sync failed: {}", e);
+                self.record_failure(dry_run, triggered_by, started_at, &e.to_string())
+                    .await
+            }
+        }
+    }
+
+    /// Sync runs, newest first
+    pub async fn list_runs(
+        &self,
+        query: &ListMedicationSyncRunsQuery,
+    ) -> Result<Vec<MedicationSyncRun>> {
+        let runs = sqlx::query_as::<_, MedicationSyncRun>(&format!(
+            "SELECT {} FROM medication_sync_runs ORDER BY started_at DESC LIMIT $1",
+            RUN_COLUMNS
+        ))
+        .bind(query.limit())
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(runs)
+    }
+
+    /// One sync run with its diff
+    pub async fn get_run(&self, run_id: Uuid) -> Result<MedicationSyncRun> {
+        sqlx::query_as::<_, MedicationSyncRun>(&format!(
+            "SELECT {} FROM medication_sync_runs WHERE id = $1",
+            RUN_COLUMNS
+        ))
+        .bind(run_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Medication sync run {} not found", run_id)))
+    }
+
+    async fn apply_import(
+        &self,
+        class_a_csv: &str,
+        atc_csv: Option<&str>,
+        dry_run: bool,
+        triggered_by: Option<Uuid>,
+    ) -> Result<SyncOutcome> {
+        let medications = parse_aifa_class_a_csv(class_a_csv);
+        if medications.is_empty() {
+            return Err(AppError::BadRequest(
+                "The AIFA list contains no medication records".to_string(),
+            ));
+        }
+        let atc_codes = atc_csv.map(parse_atc_csv).unwrap_or_default();
+
+        let mut tx = self.pool.begin().await?;
+        apply_rls_context(&mut tx, triggered_by.unwrap_or(SYSTEM_USER_ID), SYSTEM_ROLE).await?;
+
+        if !dry_run {
+            self.upsert_atc_codes(&mut tx, &atc_codes).await?;
+        }
+
+        let mut atc_by_ingredient: HashMap<String, String> = sqlx::query_as::<_, (String, String)>(
+            "SELECT UPPER(description), code FROM atc_codes WHERE level = 5",
+        )
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .collect();
+        atc_by_ingredient.extend(
+            atc_codes
+                .iter()
+                .filter(|c| c.code.len() == 7)
+                .map(|c| (c.description.to_uppercase(), c.code.clone())),
+        );
+
+        let rows = build_rows(&medications, &atc_by_ingredient);
+
+        let existing = sqlx::query_as::<_, ExistingMedication>(
+            r#"
+            SELECT id, name, generic_name, aic_code, atc_code, packages, is_active
+            FROM medications
+            WHERE source = 'AIFA' AND is_custom = false
+            "#,
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        let existing_by_name: HashMap<&str, &ExistingMedication> =
+            existing.iter().map(|m| (m.name.as_str(), m)).collect();
+        let row_names: HashSet<&str> = rows.iter().map(|r| r.name.as_str()).collect();
+
+        let mut outcome = SyncOutcome {
+            records_parsed: medications.len() as i32,
+            atc_codes_imported: if dry_run { 0 } else { atc_codes.len() as i32 },
+            ..Default::default()
+        };
+        let mut to_insert = Vec::new();
+        let mut to_update = Vec::new();
+
+        for row in &rows {
+            match existing_by_name.get(row.name.as_str()) {
+                None => {
+                    outcome.added += 1;
+                    push_capped(
+                        &mut outcome.diff.added,
+                        &mut outcome.diff.truncated,
+                        MedicationDiffEntry {
+                            name: row.name.clone(),
+                            generic_name: Some(row.generic_name.clone()),
+                            aic_code: Some(row.aic_code.clone()),
+                        },
+                    );
+                    to_insert.push(row);
+                }
+                Some(current) => {
+                    let changes = diff_medication(current, row);
+                    if changes.is_empty() {
+                        outcome.unchanged += 1;
+                    } else {
+                        outcome.updated += 1;
+                        push_capped(
+                            &mut outcome.diff.updated,
+                            &mut outcome.diff.truncated,
+                            MedicationChange {
+                                name: row.name.clone(),
+                                changes,
+                            },
+                        );
+                        to_update.push((current.id, row));
+                    }
+                }
+            }
+        }
+
+        let withdrawn: Vec<&ExistingMedication> = existing
+            .iter()
+            .filter(|m| m.is_active != Some(false) && !row_names.contains(m.name.as_str()))
+            .collect();
+        outcome.deactivated = withdrawn.len() as i32;
+        for medication in &withdrawn {
+            push_capped(
+                &mut outcome.diff.deactivated,
+                &mut outcome.diff.truncated,
+                MedicationDiffEntry {
+                    name: medication.name.clone(),
+                    generic_name: medication.generic_name.clone(),
+                    aic_code: medication.aic_code.clone(),
+                },
+            );
+        }
+
+        if dry_run {
+            tx.rollback().await?;
+            return Ok(outcome);
+        }
+
+        // Withdrawn first: a renamed medication keeps its AIC code and is
+        // reactivated by the insert below
+        let withdrawn_ids: Vec<Uuid> = withdrawn.iter().map(|m| m.id).collect();
+        sqlx::query(
+            "UPDATE medications SET is_active = false, updated_at = NOW() WHERE id = ANY($1)",
+        )
+        .bind(&withdrawn_ids)
+        .execute(&mut *tx)
+        .await?;
+
+        for row in to_insert {
+            self.insert_medication(&mut tx, row).await?;
+        }
+        for (id, row) in to_update {
+            self.update_medication(&mut tx, id, row).await?;
+        }
+
+        sqlx::query(
+            r#"
+            UPDATE medications
+            SET source_updated_at = NOW()
+            WHERE source = 'AIFA' AND is_custom = false AND is_active = true
+            "#,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(outcome)
+    }
+
+    async fn upsert_atc_codes(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        atc_codes: &[AtcCode],
+    ) -> Result<()> {
+        // Parents before children, for the parent_code foreign key
+        let mut sorted: Vec<&AtcCode> = atc_codes.iter().collect();
+        sorted.sort_by_key(|c| c.code.len());
+
+        for code in sorted {
+            let (level, parent_code) = atc_level(&code.code);
+            sqlx::query(
+                r#"
+                INSERT INTO atc_codes (code, description, level, parent_code)
+                VALUES ($1, $2, $3, (SELECT code FROM atc_codes WHERE code = $4))
+                ON CONFLICT (code) DO UPDATE SET description = EXCLUDED.description
+                "#,
+            )
+            .bind(&code.code)
+            .bind(&code.description)
+            .bind(level)
+            .bind(&parent_code)
+            .execute(&mut **tx)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn insert_medication(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        row: &AifaMedicationRow,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO medications (
+                aic_code, atc_code, name, generic_name, form, dosage_strength, route,
+                package_description, manufacturer, drug_class, is_generic,
+                is_prescription_required, common_dosages, packages, public_price,
+                source, source_updated_at, is_custom, is_active
+            )
+            VALUES (
+                $1, $2, $3, $4, $5, $6, $7, $8, $9, 'A', $10, true, $11, $12, $13,
+                'AIFA', NOW(), false, true
+            )
+            ON CONFLICT (aic_code) DO UPDATE SET
+                atc_code = COALESCE(EXCLUDED.atc_code, medications.atc_code),
+                name = EXCLUDED.name,
+                generic_name = EXCLUDED.generic_name,
+                form = EXCLUDED.form,
+                dosage_strength = EXCLUDED.dosage_strength,
+                route = EXCLUDED.route,
+                package_description = EXCLUDED.package_description,
+                manufacturer = EXCLUDED.manufacturer,
+                is_generic = EXCLUDED.is_generic,
+                common_dosages = EXCLUDED.common_dosages,
+                packages = EXCLUDED.packages,
+                public_price = EXCLUDED.public_price,
+                source_updated_at = EXCLUDED.source_updated_at,
+                is_active = true,
+                updated_at = NOW()
+            "#,
+        )
+        .bind(&row.aic_code)
+        .bind(&row.atc_code)
+        .bind(&row.name)
+        .bind(&row.generic_name)
+        .bind(row.form)
+        .bind(&row.dosage_strength)
+        .bind(row.route)
+        .bind(&row.package_description)
+        .bind(&row.manufacturer)
+        .bind(row.is_generic)
+        .bind(serde_json::to_value(&row.common_dosages).unwrap_or_default())
+        .bind(serde_json::to_value(&row.packages).unwrap_or_default())
+        .bind(row.public_price)
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn update_medication(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        id: Uuid,
+        row: &AifaMedicationRow,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE medications
+            SET aic_code = $2,
+                atc_code = COALESCE($3, atc_code),
+                generic_name = $4,
+                form = $5,
+                dosage_strength = $6,
+                route = $7,
+                package_description = $8,
+                manufacturer = $9,
+                is_generic = $10,
+                common_dosages = $11,
+                packages = $12,
+                public_price = $13,
+                is_active = true,
+                updated_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(&row.aic_code)
+        .bind(&row.atc_code)
+        .bind(&row.generic_name)
+        .bind(row.form)
+        .bind(&row.dosage_strength)
+        .bind(row.route)
+        .bind(&row.package_description)
+        .bind(&row.manufacturer)
+        .bind(row.is_generic)
+        .bind(serde_json::to_value(&row.common_dosages).unwrap_or_default())
+        .bind(serde_json::to_value(&row.packages).unwrap_or_default())
+        .bind(row.public_price)
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn record_failure(
+        &self,
+        dry_run: bool,
+        triggered_by: Option<Uuid>,
+        started_at: DateTime<Utc>,
+        error_message: &str,
+    ) -> Result<MedicationSyncRun> {
+        self.record_run(
+            dry_run,
+            MedicationSyncStatus::Failed,
+            &SyncOutcome::default(),
+            Some(error_message),
+            triggered_by,
+            started_at,
+        )
+        .await
+    }
+
+    async fn record_run(
+        &self,
+        dry_run: bool,
+        status: MedicationSyncStatus,
+        outcome: &SyncOutcome,
+        error_message: Option<&str>,
+        triggered_by: Option<Uuid>,
+        started_at: DateTime<Utc>,
+    ) -> Result<MedicationSyncRun> {
+        let diff = serde_json::to_value(&outcome.diff)
+            .map_err(|e| AppError::Internal(format!("Failed to serialize sync diff: {}", e)))?;
+
+        let run = sqlx::query_as::<_, MedicationSyncRun>(&format!(
+            r#"
+            INSERT INTO medication_sync_runs (
+                dry_run, status, records_parsed, atc_codes_imported, added_count,
+                updated_count, unchanged_count, deactivated_count, diff, error_message,
+                triggered_by, started_at, completed_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, NOW())
+            RETURNING {}
+            "#,
+            RUN_COLUMNS
+        ))
+        .bind(dry_run)
+        .bind(status.as_str())
+        .bind(outcome.records_parsed)
+        .bind(outcome.atc_codes_imported)
+        .bind(outcome.added)
+        .bind(outcome.updated)
+        .bind(outcome.unchanged)
+        .bind(outcome.deactivated)
+        .bind(&diff)
+        .bind(error_message)
+        .bind(triggered_by)
+        .bind(started_at)
+        .fetch_one(&self.pool)
+        .await?;
+
+        let _ = AuditLog::create(
+            &self.pool,
+            CreateAuditLog {
+                user_id: triggered_by,
+                action: if dry_run {
+                    AuditAction::Read
+                } else {
+                    AuditAction::Update
+                },
+                entity_type: EntityType::MedicationSync,
+                entity_id: Some(run.id.to_string()),
+                changes: Some(serde_json::json!({
+                    "dry_run": dry_run,
+                    "status": run.status,
+                    "records_parsed": run.records_parsed,
+                    "added": run.added_count,
+                    "updated": run.updated_count,
+                    "deactivated": run.deactivated_count,
+                })),
+                ip_address: None,
+                user_agent: None,
+                request_id: None,
+            },
+        )
+        .await;
+
+        Ok(run)
+    }
+}
+
+fn push_capped<T>(list: &mut Vec<T>, truncated: &mut bool, item: T) {
+    if list.len() < DIFF_LIMIT {
+        list.push(item);
+    } else {
+        *truncated = true;
+    }
+}
+
+/// Download an AIFA file
+async fn download(url: &str) -> Result<String> {
+    let client = reqwest::Client::builder()
+        .timeout(DOWNLOAD_TIMEOUT)
+        .build()
+        .map_err(|e| AppError::Internal(format!("Failed to build HTTP client: {}", e)))?;
+
+    let bytes = client
+        .get(url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| AppError::Internal(format!("Failed to download {}: {}", url, e)))?
+        .bytes()
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to download {}: {}", url, e)))?;
+
+    Ok(decode_csv(&bytes))
+}
+
+/// Spawn the scheduled AIFA re-sync as a background task
+///
+/// The interval is read from `medication_sync_interval_days` before each
+/// run; 0 disables the sync until the setting changes.
+pub fn spawn_medication_sync_job(
+    pool: PgPool,
+    settings_service: Arc<SettingsService>,
+    cache: ReferenceCache,
+) {
+    let service = MedicationImportService::new(pool).with_cache(cache);
+
+    tokio::spawn(async move {
+        loop {
+            let interval_days = match settings_service
+                .get_setting("medication_sync_interval_days")
+                .await
+            {
+                Ok(Some(setting)) => setting
+                    .setting_value
+                    .as_i64()
+                    .unwrap_or(DEFAULT_INTERVAL_DAYS),
+                _ => DEFAULT_INTERVAL_DAYS,
+            };
+
+            if interval_days <= 0 {
+                sleep(Duration::from_secs(3600)).await;
+                continue;
+            }
+
+            sleep(Duration::from_secs(interval_days as u64 * 86_400)).await;
+
+            match service
+                .sync_from_settings(&settings_service, false, None)
+                .await
+            {
+                Ok(run) if run.status == MedicationSyncStatus::Failed.as_str() => warn!(
+                    "Scheduled medication sync failed: {}",
+                    run.error_message.unwrap_or_default()
+                ),
+                Ok(_) => {}
+                Err(e) => error!("Scheduled medication sync failed: {}", e),
+            }
+        }
+    });
+
+    info!("Medication sync job spawned as background task");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLASS_A_CSV: &str = "\
+Principio Attivo;Descrizione Gruppo;Denominazione e Confezione;Prezzo;Titolare AIC;AIC;Codice Gruppo Equivalenza;Generico
+AMOXICILLINA;AMOXICILLINA 1G 12 UNITA' USO ORALE;ZIMOX*12 cpr 1 g;8,50;PFIZER;023658024;AB1;
+AMOXICILLINA;AMOXICILLINA 500MG 12 UNITA' USO ORALE;ZIMOX*12 cps 500 mg;5,20;PFIZER;023658012;AB2;
+RAMIPRIL;RAMIPRIL 5MG 28 UNITA' USO ORALE;RAMIPRIL TEVA*28 cpr 5 mg;3,10;TEVA;037281015;CF1;X
+incomplete;line
+";
+
+    fn atc() -> HashMap<String, String> {
+        HashMap::from([("AMOXICILLINA".to_string(), "J01CA04".to_string())])
+    }
+
+    #[test]
+    fn test_parse_class_a_csv() {
+        let medications = parse_aifa_class_a_csv(CLASS_A_CSV);
+        assert_eq!(medications.len(), 3);
+        assert_eq!(medications[0].commercial_name, "ZIMOX");
+        assert_eq!(medications[0].package_description, "12 cpr 1 g");
+        assert_eq!(medications[0].price, Some(8.5));
+        assert_eq!(medications[0].equivalence_group.as_deref(), Some("AB1"));
+        assert!(medications[2].is_generic);
+    }
+
+    #[test]
+    fn test_build_rows_groups_packages_by_name() {
+        let rows = build_rows(&parse_aifa_class_a_csv(CLASS_A_CSV), &atc());
+        assert_eq!(rows.len(), 2);
+
+        let zimox = rows.iter().find(|r| r.name == "ZIMOX").unwrap();
+        assert_eq!(zimox.aic_code, "023658012");
+        assert_eq!(zimox.packages.len(), 2);
+        assert_eq!(zimox.public_price, Some(5.2));
+        assert_eq!(zimox.atc_code.as_deref(), Some("J01CA04"));
+        assert_eq!(zimox.form, Some("Capsule"));
+        assert_eq!(zimox.route, Some("ORAL"));
+
+        let ramipril = rows.iter().find(|r| r.name == "RAMIPRIL TEVA").unwrap();
+        assert_eq!(ramipril.atc_code, None);
+    }
+
+    #[test]
+    fn test_diff_reports_price_and_package_changes() {
+        let rows = build_rows(&parse_aifa_class_a_csv(CLASS_A_CSV), &atc());
+        let zimox = rows.iter().find(|r| r.name == "ZIMOX").unwrap();
+
+        let mut old_packages = zimox.packages.clone();
+        old_packages[0].price = Some(4.9);
+        old_packages[1].aic_code = "023658999".to_string();
+        let existing = ExistingMedication {
+            id: Uuid::new_v4(),
+            name: "ZIMOX".to_string(),
+            generic_name: Some("AMOXICILLINA".to_string()),
+            aic_code: Some("023658012".to_string()),
+            atc_code: Some("J01CA04".to_string()),
+            packages: serde_json::to_value(&old_packages).unwrap(),
+            is_active: Some(true),
+        };
+
+        let changes = diff_medication(&existing, zimox);
+        let fields: Vec<&str> = changes.iter().map(|c| c.field.as_str()).collect();
+        assert_eq!(fields, vec!["price", "package_added", "package_removed"]);
+        assert_eq!(changes[0].old.as_deref(), Some("4.90"));
+        assert_eq!(changes[0].new.as_deref(), Some("5.20"));
+
+        let unchanged = ExistingMedication {
+            packages: serde_json::to_value(&zimox.packages).unwrap(),
+            ..existing
+        };
+        assert!(diff_medication(&unchanged, zimox).is_empty());
+    }
+
+    #[test]
+    fn test_parse_atc_csv_and_levels() {
+        let codes =
+            parse_atc_csv("codice_atc;descrizione\nJ01CA04;AMOXICILLINA\nj01;ANTIBATTERICI\n;\n");
+        assert_eq!(codes.len(), 2);
+        assert_eq!(codes[1].code, "J01");
+        assert_eq!(atc_level("J01CA04"), (5, Some("J01CA".to_string())));
+        assert_eq!(atc_level("J"), (1, None));
+    }
+
+    #[test]
+    fn test_decode_csv_falls_back_to_latin1() {
+        assert_eq!(decode_csv("UNITA'".as_bytes()), "UNITA'");
+        assert_eq!(decode_csv(&[b'P', b'I', 0xD9]), "PIÙ");
+    }
+}
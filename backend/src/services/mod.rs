@@ -5,46 +5,170 @@
  */
 
 pub mod appointment_service;
+pub mod attachment_ocr_service;
+pub mod audit_chain_service;
 pub mod audit_log_service;
 pub mod auth_service;
+pub mod batch_service;
+pub mod booking_service;
+pub mod branding_service;
+pub mod calendar_feed_service;
+pub mod captcha_service;
+pub mod clinical_calculators;
+pub mod contact_propagation;
+pub mod delegation_service;
 pub mod document_service;
+pub mod drug_allergy_check;
+pub mod e_prescription_service;
 pub mod email_service;
+pub mod field_reencryption_service;
 pub mod file_service;
 pub mod holiday_service;
+pub mod imaging_service;
+pub mod impersonation_service;
+pub mod invitation_service;
+pub mod janitor_service;
 pub mod jwt_service;
+pub mod lab_service;
 pub mod notification_scheduler;
 pub mod notification_service;
+pub mod ocr_engine;
+pub mod password_policy_service;
+pub mod patient_allergy_service;
+pub mod patient_attachment_service;
+pub mod patient_chart_service;
+pub mod patient_cohort_service;
+pub mod patient_communication_service;
+pub mod patient_consent_service;
+pub mod patient_erasure_service;
+pub mod patient_export_service;
+pub mod patient_message_service;
+pub mod patient_merge_service;
+pub mod patient_photo_service;
+pub mod patient_portal_service;
+pub mod patient_problem_service;
 pub mod patient_service;
+pub mod overbooking_service;
+pub mod prescription_refill_service;
 pub mod prescription_service;
 pub mod prescription_template_service;
+pub mod quality_indicator_service;
+pub mod recall_service;
+pub mod referral_service;
+#[cfg(feature = "report-export")]
+pub mod report_chart;
 pub mod report_export_service;
 pub mod report_service;
+pub mod report_subscription_service;
+pub mod report_view_service;
+pub mod rate_limit_quota_service;
+pub mod reference_cache;
+pub mod resource_service;
+pub mod retention_service;
+pub mod medication_favorite_service;
+pub mod medication_import_service;
 pub mod settings_service;
+pub mod siem_exporter;
+pub mod sistema_ts_client;
+pub mod telemetry_service;
+pub mod televisit_service;
+pub mod transcription;
+pub mod trash_service;
+pub mod trusted_device_service;
+pub mod template_filters;
+pub mod visit_addendum_service;
+pub mod visit_attachment_service;
+pub mod visit_calculation_service;
+pub mod visit_cosign_service;
 pub mod visit_diagnosis_service;
+pub mod visit_search_service;
+pub mod visit_survey_service;
 pub mod visit_service;
 pub mod visit_template_service;
+pub mod visit_transcription_service;
+pub mod video_room;
+pub mod vitals_service;
+pub mod waiting_room_service;
+pub mod waitlist_service;
 pub mod working_hours_service;
 pub mod health_service;
 pub mod drug_interaction_service;
 
-pub use appointment_service::AppointmentService;
+pub use appointment_service::{spawn_appointment_text_encryption_backfill, AppointmentService};
+pub use attachment_ocr_service::spawn_attachment_ocr_job;
 pub use auth_service::{AuthService, LoginRequest, LoginResponse};
-pub use document_service::DocumentService;
-pub use email_service::{generate_document_email_body, EmailService};
-pub use jwt_service::{Claims, JwtService, TokenPair};
-pub use patient_service::PatientService;
+pub use batch_service::BatchService;
+pub use booking_service::BookingService;
+pub use branding_service::BrandingService;
+pub use calendar_feed_service::CalendarFeedService;
+pub use captcha_service::CaptchaService;
+pub use delegation_service::DelegationService;
+pub use document_service::{DocumentRetry, DocumentService};
+pub use e_prescription_service::EPrescriptionService;
+pub use email_service::{generate_document_email_body, EmailBranding, EmailService};
+pub use field_reencryption_service::{spawn_field_reencryption_job, FieldReencryptionService};
+pub use imaging_service::ImagingService;
+pub use jwt_service::{
+    ActorClaim, BookingSlotClaims, Claims, DeviceClaims, JwtService, PortalClaims, TokenPair,
+};
+pub use lab_service::LabService;
+pub use password_policy_service::{PasswordPolicy, PasswordPolicyService};
+pub use patient_allergy_service::PatientAllergyService;
+pub use patient_attachment_service::PatientAttachmentService;
+pub use patient_chart_service::PatientChartService;
+pub use patient_cohort_service::PatientCohortService;
+pub use patient_communication_service::PatientCommunicationService;
+pub use patient_consent_service::PatientConsentService;
+pub use patient_erasure_service::PatientErasureService;
+pub use patient_export_service::{spawn_patient_export_cleanup, PatientExportService};
+pub use patient_merge_service::PatientMergeService;
+pub use patient_message_service::PatientMessageService;
+pub use patient_photo_service::PatientPhotoService;
+pub use patient_portal_service::PatientPortalService;
+pub use patient_problem_service::{PatientProblemService, PromotedProblem};
+pub use patient_service::{spawn_patient_blind_index_backfill, PatientService};
+pub use prescription_refill_service::PrescriptionRefillService;
 pub use prescription_service::PrescriptionService;
 pub use prescription_template_service::PrescriptionTemplateService;
-pub use report_export_service::{ExportResponse, ReportExportService};
+pub use quality_indicator_service::QualityIndicatorService;
+pub use overbooking_service::OverbookingService;
+pub use recall_service::{spawn_recall_job, RecallService};
+pub use referral_service::ReferralService;
+pub use report_export_service::{CsvRecord, ExportResponse, ReportExportService};
 pub use report_service::ReportService;
+pub use report_subscription_service::{
+    spawn_report_subscription_job, ReportSubscriptionService,
+};
+pub use report_view_service::{spawn_report_view_refresh, ReportViewService};
+pub use reference_cache::{CacheNamespace, ReferenceCache};
+pub use resource_service::ResourceService;
+pub use rate_limit_quota_service::{spawn_rate_limit_quota_refresh, RateLimitQuotaService};
+pub use retention_service::{spawn_retention_job, RetentionService};
+pub use trash_service::TrashService;
+pub use medication_favorite_service::MedicationFavoriteService;
+pub use medication_import_service::{spawn_medication_sync_job, MedicationImportService};
+pub use visit_addendum_service::VisitAddendumService;
+pub use visit_attachment_service::VisitAttachmentService;
+pub use visit_calculation_service::VisitCalculationService;
+pub use visit_cosign_service::VisitCosignService;
 pub use visit_diagnosis_service::VisitDiagnosisService;
+pub use visit_search_service::{spawn_visit_search_indexer, VisitSearchService};
+pub use visit_survey_service::VisitSurveyService;
 pub use visit_service::{
     VisitSearchFilter, VisitService,
 };
 pub use settings_service::SettingsService;
+pub use sistema_ts_client::SistemaTsClient;
 pub use visit_template_service::VisitTemplateService;
+pub use visit_transcription_service::spawn_visit_transcription_job;
+pub use vitals_service::VitalsService;
+pub use waiting_room_service::WaitingRoomService;
+pub use waitlist_service::{spawn_waitlist_job, WaitlistService};
 pub use working_hours_service::WorkingHoursService;
 pub use holiday_service::HolidayService;
+pub use impersonation_service::ImpersonationService;
+pub use invitation_service::InvitationService;
+pub use audit_chain_service::{spawn_audit_chain_verifier, AuditChainService};
 pub use audit_log_service::AuditLogService;
 pub use file_service::FileUploadService;
 pub use health_service::SystemHealthService;
@@ -54,3 +178,8 @@ pub use drug_interaction_service::{
 };
 pub use notification_service::NotificationService;
 pub use notification_scheduler::spawn_notification_scheduler;
+pub use janitor_service::spawn_janitor;
+pub use telemetry_service::{spawn_telemetry_reporter, TelemetryService};
+pub use televisit_service::TelevisitService;
+pub use video_room::{build_video_room_provider, VideoRoomProvider};
+pub use trusted_device_service::TrustedDeviceService;
@@ -3,21 +3,35 @@
  *
  * Background task that handles automatic notification scheduling:
  * - Runs daily at a configurable time (default 8:00 AM)
- * - Generates appointment reminders based on patient preferences
+ * - Generates appointment reminders based on patient preferences, with
+ *   links to confirm or cancel
+ * - Reminds referring doctors of referrals overdue for a specialist response
+ * - Sends birthday greetings and preventive screening reminders, when the
+ *   practice enables them
+ * - Sends a satisfaction survey after completed appointments, when the
+ *   practice enables it
  * - Processes pending notifications
  * - Retries failed notifications
  *
  * Milestone 15, Phase 5.2
  */
 
-use crate::services::{notification_service::NotificationService, SettingsService};
+use crate::db::rls::{apply_rls_context, SYSTEM_ROLE, SYSTEM_USER_ID};
+use crate::models::visit_survey::{
+    VISIT_SURVEY_LOOKBACK_DAYS, VISIT_SURVEY_PATIENT_INTERVAL_DAYS, VISIT_SURVEY_VALID_DAYS,
+};
+use crate::models::{
+    is_birthday, parse_screening_programs, ScreeningProgram, SCREENING_PROGRAMS_SETTING,
+};
+use crate::services::{notification_service::NotificationService, BrandingService, SettingsService};
 use crate::utils::encryption::EncryptionKey;
 use anyhow::Result;
-use chrono::{DateTime, Duration, NaiveTime, TimeZone, Utc};
-use sqlx::{PgPool, Postgres, Transaction};
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, TimeZone, Utc};
+use sqlx::PgPool;
+use std::collections::HashSet;
 use std::sync::Arc;
 use tokio::time::{sleep, Duration as TokioDuration};
-use tracing::{debug, error, info, warn};
+use tracing::{debug, error, info, info_span, warn, Instrument};
 use uuid::Uuid;
 
 /// Scheduler configuration loaded from settings
@@ -31,8 +45,24 @@ pub struct SchedulerConfig {
     pub batch_size: i64,
     /// Whether to auto-retry failed notifications
     pub retry_failed_enabled: bool,
+    /// Days between reminders about the same overdue referral
+    pub referral_reminder_interval_days: i64,
+    /// Whether to greet patients on their birthday
+    pub birthday_greetings_enabled: bool,
+    /// Message of the birthday greeting
+    pub birthday_greeting_message: String,
+    /// Whether to remind patients of the screening programs
+    pub screening_reminders_enabled: bool,
+    /// Screening programs patients are reminded of
+    pub screening_programs: Vec<ScreeningProgram>,
+    /// Whether to survey patients after completed appointments
+    pub visit_surveys_enabled: bool,
 }
 
+/// Birthday greeting used when the setting is missing or empty
+const DEFAULT_BIRTHDAY_GREETING: &str =
+    "Everyone at the practice wishes you a very happy birthday and a healthy year ahead.";
+
 impl Default for SchedulerConfig {
     fn default() -> Self {
         Self {
@@ -40,10 +70,26 @@ impl Default for SchedulerConfig {
             reminder_time: "08:00".to_string(),
             batch_size: 50,
             retry_failed_enabled: true,
+            referral_reminder_interval_days: 7,
+            birthday_greetings_enabled: false,
+            birthday_greeting_message: DEFAULT_BIRTHDAY_GREETING.to_string(),
+            screening_reminders_enabled: false,
+            screening_programs: Vec::new(),
+            visit_surveys_enabled: false,
         }
     }
 }
 
+/// An active patient who accepts email, with the details preventive
+/// reminders need (decrypted)
+struct ReminderRecipient {
+    patient_id: Uuid,
+    name: String,
+    email: String,
+    gender: String,
+    date_of_birth: NaiveDate,
+}
+
 /// Notification Scheduler
 ///
 /// Background service that manages automatic notification scheduling
@@ -54,14 +100,6 @@ pub struct NotificationScheduler {
     encryption_key: EncryptionKey,
 }
 
-/// System user ID for scheduler operations
-/// Uses testadmin user ID - in production this should be a dedicated system user
-/// TODO: Create a dedicated "system" user in production with a known UUID
-const SYSTEM_USER_ID: Uuid = Uuid::from_u128(0x0bd21b8d_b27c_452e_a4a8_1e2f020d880a);
-
-/// System role for scheduler operations
-const SYSTEM_ROLE: &str = "ADMIN";
-
 impl NotificationScheduler {
     /// Create a new notification scheduler
     pub fn new(
@@ -78,23 +116,6 @@ impl NotificationScheduler {
         }
     }
 
-    /// Set RLS context for scheduler operations
-    ///
-    /// Uses a system user with ADMIN role to access all records
-    async fn set_rls_context(tx: &mut Transaction<'_, Postgres>) -> Result<()> {
-        // Use set_config() for parameterized queries (security best practice)
-        sqlx::query("SELECT set_config('app.current_user_id', $1, true)")
-            .bind(SYSTEM_USER_ID.to_string())
-            .execute(&mut **tx)
-            .await?;
-        sqlx::query("SELECT set_config('app.current_user_role', $1, true)")
-            .bind(SYSTEM_ROLE)
-            .execute(&mut **tx)
-            .await?;
-
-        Ok(())
-    }
-
     /// Load scheduler configuration from database settings
     async fn load_config(&self) -> SchedulerConfig {
         let mut config = SchedulerConfig::default();
@@ -139,6 +160,69 @@ impl NotificationScheduler {
             }
         }
 
+        // Load referral_reminder_interval_days
+        if let Ok(Some(setting)) = self
+            .settings_service
+            .get_setting("referral_reminder_interval_days")
+            .await
+        {
+            if let Some(value) = setting.setting_value.as_i64().filter(|v| *v > 0) {
+                config.referral_reminder_interval_days = value;
+            }
+        }
+
+        // Load preventive reminder settings
+        if let Ok(Some(setting)) = self
+            .settings_service
+            .get_setting("birthday_greetings_enabled")
+            .await
+        {
+            if let Some(value) = setting.setting_value.as_bool() {
+                config.birthday_greetings_enabled = value;
+            }
+        }
+
+        if let Ok(Some(setting)) = self
+            .settings_service
+            .get_setting("birthday_greeting_message")
+            .await
+        {
+            if let Some(value) = setting.setting_value.as_str().filter(|v| !v.trim().is_empty()) {
+                config.birthday_greeting_message = value.to_string();
+            }
+        }
+
+        if let Ok(Some(setting)) = self
+            .settings_service
+            .get_setting("screening_reminders_enabled")
+            .await
+        {
+            if let Some(value) = setting.setting_value.as_bool() {
+                config.screening_reminders_enabled = value;
+            }
+        }
+
+        if let Ok(Some(setting)) = self
+            .settings_service
+            .get_setting(SCREENING_PROGRAMS_SETTING)
+            .await
+        {
+            match parse_screening_programs(&setting.setting_value) {
+                Ok(programs) => config.screening_programs = programs,
+                Err(e) => warn!("Ignoring invalid screening programs setting: {}", e),
+            }
+        }
+
+        if let Ok(Some(setting)) = self
+            .settings_service
+            .get_setting("visit_surveys_enabled")
+            .await
+        {
+            if let Some(value) = setting.setting_value.as_bool() {
+                config.visit_surveys_enabled = value;
+            }
+        }
+
         config
     }
 
@@ -188,10 +272,11 @@ impl NotificationScheduler {
     ///
     /// This spawns a background task that:
     /// 1. Waits until the configured reminder time
-    /// 2. Generates appointment reminders for upcoming appointments
-    /// 3. Processes pending notifications
-    /// 4. Retries failed notifications (if enabled)
-    /// 5. Loops back to step 1
+    /// 2. Generates appointment and referral reminders
+    /// 3. Generates birthday greetings and screening reminders (if enabled)
+    /// 4. Processes pending notifications
+    /// 5. Retries failed notifications (if enabled)
+    /// 6. Loops back to step 1
     pub async fn start(self: Arc<Self>) {
         info!("Starting notification scheduler background task");
 
@@ -223,12 +308,21 @@ impl NotificationScheduler {
                 continue;
             }
 
-            info!("Running scheduled notification tasks");
+            // Each run gets its own ID; notifications queued by an API request are
+            // processed under that request's correlation ID (see process_notification)
+            let run_id = Uuid::new_v4();
+            let span = info_span!("scheduler_run", run_id = %run_id);
+
+            async {
+                info!("Running scheduled notification tasks");
 
-            // Run the scheduler tasks
-            if let Err(e) = self.run_scheduler_tasks(&config).await {
-                error!("Scheduler run failed: {}", e);
+                // Run the scheduler tasks
+                if let Err(e) = self.run_scheduler_tasks(&config).await {
+                    error!("Scheduler run failed: {}", e);
+                }
             }
+            .instrument(span)
+            .await;
 
             // Sleep for 60 seconds after running to prevent tight loop
             // This ensures we move past the scheduled time before recalculating next run
@@ -243,12 +337,57 @@ impl NotificationScheduler {
         let reminders_created = self.generate_appointment_reminders(config.batch_size).await?;
         info!("Created {} appointment reminders", reminders_created);
 
-        // 2. Process pending notifications
+        // 2. Remind doctors of overdue referrals
+        info!("Generating referral reminders...");
+        let referral_reminders = self
+            .generate_referral_reminders(config.batch_size, config.referral_reminder_interval_days)
+            .await?;
+        info!("Created {} referral reminders", referral_reminders);
+
+        // 3. Birthday greetings and screening reminders (if enabled)
+        if config.birthday_greetings_enabled || config.screening_reminders_enabled {
+            let recipients = self.load_reminder_recipients().await?;
+            let today = Utc::now().date_naive();
+
+            if config.birthday_greetings_enabled {
+                info!("Generating birthday greetings...");
+                let greetings = self
+                    .generate_birthday_greetings(
+                        &recipients,
+                        &config.birthday_greeting_message,
+                        today,
+                    )
+                    .await?;
+                info!("Created {} birthday greetings", greetings);
+            }
+
+            if config.screening_reminders_enabled {
+                info!("Generating screening reminders...");
+                let screening_reminders = self
+                    .generate_screening_reminders(
+                        &recipients,
+                        &config.screening_programs,
+                        config.batch_size,
+                        today,
+                    )
+                    .await?;
+                info!("Created {} screening reminders", screening_reminders);
+            }
+        }
+
+        // 4. Post-visit surveys (if enabled)
+        if config.visit_surveys_enabled {
+            info!("Generating visit surveys...");
+            let surveys = self.generate_visit_surveys(config.batch_size).await?;
+            info!("Created {} visit surveys", surveys);
+        }
+
+        // 5. Process pending notifications
         info!("Processing pending notifications...");
         let processed = self.process_pending_notifications(config.batch_size).await?;
         info!("Processed {} pending notifications", processed);
 
-        // 3. Retry failed notifications (if enabled)
+        // 6. Retry failed notifications (if enabled)
         if config.retry_failed_enabled {
             info!("Retrying failed notifications...");
             let retried = self.retry_failed_notifications(config.batch_size / 2).await?;
@@ -269,7 +408,7 @@ impl NotificationScheduler {
 
         // Start transaction and set RLS context
         let mut tx = self.pool.begin().await?;
-        Self::set_rls_context(&mut tx).await?;
+        apply_rls_context(&mut tx, SYSTEM_USER_ID, SYSTEM_ROLE).await?;
 
         // Query appointments that need reminders
         // Join with patient_notification_preferences to get reminder settings
@@ -280,7 +419,6 @@ impl NotificationScheduler {
                 a.patient_id,
                 a.scheduled_start,
                 a.type as appointment_type,
-                a.reason,
                 p.first_name as patient_first_name,
                 p.last_name as patient_last_name,
                 p.email as patient_email,
@@ -307,6 +445,14 @@ impl NotificationScheduler {
         .fetch_all(&mut *tx)
         .await?;
 
+        // Reminders carry confirm/cancel links when the practice has a public URL
+        let public_base_url = BrandingService::new(self.pool.clone())
+            .load_settings()
+            .await
+            .ok()
+            .and_then(|s| s.public_base_url)
+            .filter(|url| !url.trim().is_empty());
+
         for appt in appointments {
             // Check if reminder should be sent today
             let reminder_days = appt.reminder_days_before.unwrap_or(1) as i64;
@@ -398,11 +544,22 @@ impl NotificationScheduler {
                 appt.provider_last_name
             );
 
+            let response_links = public_base_url.as_deref().map(|base_url| {
+                NotificationService::appointment_response_links(
+                    &self.encryption_key,
+                    base_url,
+                    appt.appointment_id,
+                    appt.patient_id,
+                    appt.scheduled_start,
+                )
+            });
+
             let (subject, body) = crate::services::notification_service::generate_appointment_reminder_email(
                 &patient_name,
                 &appt.scheduled_start,
                 &provider_name,
                 &appt.appointment_type,
+                response_links.as_ref(),
             );
 
             // Create notification in queue
@@ -442,6 +599,564 @@ impl NotificationScheduler {
         Ok(created)
     }
 
+    /// Remind referring doctors of referrals overdue for a response
+    ///
+    /// Queues one REFERRAL_REMINDER email per SENT referral past its response
+    /// due date, to the doctor who made it, at most once every
+    /// `interval_days`. The email names the patient and the specialty but
+    /// not the clinical reason.
+    async fn generate_referral_reminders(&self, limit: i64, interval_days: i64) -> Result<i64> {
+        let mut created = 0;
+
+        let mut tx = self.pool.begin().await?;
+        apply_rls_context(&mut tx, SYSTEM_USER_ID, SYSTEM_ROLE).await?;
+
+        #[allow(clippy::type_complexity)]
+        let referrals: Vec<(
+            Uuid,
+            Uuid,
+            String,
+            chrono::NaiveDate,
+            Uuid,
+            String,
+            String,
+            String,
+            String,
+            String,
+        )> = sqlx::query_as(
+            r#"
+            SELECT r.id, r.patient_id, r.specialty, r.response_due_date,
+                   u.id, u.email, u.first_name, u.last_name,
+                   p.first_name, p.last_name
+            FROM referrals r
+            INNER JOIN users u ON u.id = r.created_by
+            INNER JOIN patients p ON p.id = r.patient_id
+            WHERE r.deleted_at IS NULL
+              AND r.status = 'SENT'
+              AND r.response_due_date < CURRENT_DATE
+              AND (r.last_reminder_at IS NULL
+                   OR r.last_reminder_at <= NOW() - make_interval(days => $1::INTEGER))
+              AND u.is_active
+              AND p.anonymized_at IS NULL
+            ORDER BY r.response_due_date
+            LIMIT $2
+            "#,
+        )
+        .bind(interval_days as i32)
+        .bind(limit)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let today = Utc::now().date_naive();
+        for (
+            referral_id,
+            patient_id,
+            specialty,
+            due_date,
+            doctor_id,
+            doctor_email,
+            doctor_first_name,
+            doctor_last_name,
+            patient_first_name,
+            patient_last_name,
+        ) in referrals
+        {
+            let patient_name = match (
+                self.encryption_key.decrypt(&patient_first_name),
+                self.encryption_key.decrypt(&patient_last_name),
+            ) {
+                (Ok(first), Ok(last)) => format!("{} {}", first, last),
+                _ => {
+                    warn!(
+                        "Failed to decrypt patient name for referral {}, skipping reminder",
+                        referral_id
+                    );
+                    continue;
+                }
+            };
+            let doctor_name = format!("Dr. {} {}", doctor_first_name, doctor_last_name);
+
+            let (subject, body) =
+                crate::services::notification_service::generate_referral_reminder_email(
+                    &doctor_name,
+                    &patient_name,
+                    &specialty,
+                    due_date,
+                    (today - due_date).num_days(),
+                );
+
+            sqlx::query(
+                r#"
+                INSERT INTO notification_queue (
+                    id, patient_id, user_id, notification_type, delivery_method,
+                    recipient_email, recipient_name, subject, message_body,
+                    scheduled_for, priority, status, created_by
+                )
+                VALUES ($1, $2, $3, 'REFERRAL_REMINDER', 'EMAIL', $4, $5, $6, $7, NOW(), 5, 'PENDING', $8)
+                "#,
+            )
+            .bind(Uuid::new_v4())
+            .bind(patient_id)
+            .bind(doctor_id)
+            .bind(&doctor_email)
+            .bind(&doctor_name)
+            .bind(&subject)
+            .bind(&body)
+            .bind(SYSTEM_USER_ID)
+            .execute(&mut *tx)
+            .await?;
+
+            sqlx::query(
+                r#"
+                UPDATE referrals
+                SET last_reminder_at = NOW(), reminder_count = reminder_count + 1
+                WHERE id = $1
+                "#,
+            )
+            .bind(referral_id)
+            .execute(&mut *tx)
+            .await?;
+
+            info!(
+                "Created reminder for overdue referral {} (doctor: {})",
+                referral_id, doctor_id
+            );
+            created += 1;
+        }
+
+        tx.commit().await?;
+
+        Ok(created)
+    }
+
+    /// Active patients who accept email notifications
+    ///
+    /// Patients whose email, name or date of birth cannot be decrypted are
+    /// left out.
+    async fn load_reminder_recipients(&self) -> Result<Vec<ReminderRecipient>> {
+        let mut tx = self.pool.begin().await?;
+        apply_rls_context(&mut tx, SYSTEM_USER_ID, SYSTEM_ROLE).await?;
+
+        let rows: Vec<(Uuid, String, String, String, String, Option<String>)> = sqlx::query_as(
+            r#"
+            SELECT p.id, p.first_name, p.last_name, p.date_of_birth, p.gender,
+                   COALESCE(pnp.email_address_override, p.email)
+            FROM patients p
+            LEFT JOIN patient_notification_preferences pnp ON pnp.patient_id = p.id
+            WHERE p.status = 'ACTIVE'
+              AND p.deleted_at IS NULL
+              AND p.merged_at IS NULL
+              AND p.anonymized_at IS NULL
+              AND (pnp.email_enabled IS NULL OR pnp.email_enabled = true)
+            "#,
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        let mut recipients = Vec::with_capacity(rows.len());
+        for (patient_id, first_name, last_name, date_of_birth, gender, email) in rows {
+            let Some(email) = email else {
+                continue;
+            };
+            let decrypted = (
+                self.encryption_key.decrypt(&first_name),
+                self.encryption_key.decrypt(&last_name),
+                self.encryption_key.decrypt(&date_of_birth),
+                self.encryption_key.decrypt(&email),
+            );
+            let (Ok(first_name), Ok(last_name), Ok(date_of_birth), Ok(email)) = decrypted else {
+                warn!(
+                    "Failed to decrypt patient {}, skipping preventive reminders",
+                    patient_id
+                );
+                continue;
+            };
+            let Ok(date_of_birth) = NaiveDate::parse_from_str(&date_of_birth, "%Y-%m-%d") else {
+                warn!(
+                    "Invalid date of birth for patient {}, skipping preventive reminders",
+                    patient_id
+                );
+                continue;
+            };
+            if email.trim().is_empty() {
+                continue;
+            }
+
+            recipients.push(ReminderRecipient {
+                patient_id,
+                name: format!("{} {}", first_name, last_name),
+                email,
+                gender,
+                date_of_birth,
+            });
+        }
+
+        Ok(recipients)
+    }
+
+    /// Queue a BIRTHDAY_GREETING to each recipient whose birthday is today
+    ///
+    /// The greeting records the birthday in its metadata, so a patient is
+    /// greeted once even if the scheduler runs again the same day.
+    async fn generate_birthday_greetings(
+        &self,
+        recipients: &[ReminderRecipient],
+        message: &str,
+        today: NaiveDate,
+    ) -> Result<i64> {
+        let birthdays: Vec<&ReminderRecipient> = recipients
+            .iter()
+            .filter(|recipient| is_birthday(recipient.date_of_birth, today))
+            .collect();
+        if birthdays.is_empty() {
+            return Ok(0);
+        }
+
+        let patient_ids: Vec<Uuid> = birthdays.iter().map(|r| r.patient_id).collect();
+        let mut tx = self.pool.begin().await?;
+        apply_rls_context(&mut tx, SYSTEM_USER_ID, SYSTEM_ROLE).await?;
+        let greeted: HashSet<Uuid> = sqlx::query_scalar(
+            r#"
+            SELECT patient_id FROM notification_queue
+            WHERE notification_type = 'BIRTHDAY_GREETING'
+              AND patient_id = ANY($1)
+              AND metadata->>'birthday' = $2
+            "#,
+        )
+        .bind(&patient_ids)
+        .bind(today.format("%Y-%m-%d").to_string())
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .collect();
+        tx.commit().await?;
+
+        let mut created = 0;
+        for recipient in birthdays {
+            if greeted.contains(&recipient.patient_id) {
+                debug!(
+                    "Skipping birthday greeting for patient {} - already queued",
+                    recipient.patient_id
+                );
+                continue;
+            }
+
+            match self
+                .notification_service
+                .queue_birthday_greeting(
+                    recipient.patient_id,
+                    &recipient.email,
+                    &recipient.name,
+                    message,
+                    today,
+                    SYSTEM_USER_ID,
+                )
+                .await
+            {
+                Ok(_) => created += 1,
+                Err(e) => warn!(
+                    "Failed to queue birthday greeting for patient {}: {}",
+                    recipient.patient_id, e
+                ),
+            }
+        }
+
+        Ok(created)
+    }
+
+    /// Queue a SCREENING_REMINDER to each recipient due for a screening
+    /// program
+    ///
+    /// A recipient is due when the program applies to their sex and age,
+    /// and within the program interval there was neither a matching imaging
+    /// or lab order (or lab result) nor a reminder for the program. At most
+    /// `limit` reminders are queued per run; the others follow on later runs.
+    async fn generate_screening_reminders(
+        &self,
+        recipients: &[ReminderRecipient],
+        programs: &[ScreeningProgram],
+        limit: i64,
+        today: NaiveDate,
+    ) -> Result<i64> {
+        let mut created = 0;
+
+        for program in programs {
+            if created >= limit {
+                break;
+            }
+
+            let candidates: Vec<&ReminderRecipient> = recipients
+                .iter()
+                .filter(|recipient| {
+                    today
+                        .years_since(recipient.date_of_birth)
+                        .is_some_and(|age| program.applies_to(&recipient.gender, age, today))
+                })
+                .collect();
+            if candidates.is_empty() {
+                continue;
+            }
+
+            let patient_ids: Vec<Uuid> = candidates.iter().map(|r| r.patient_id).collect();
+            let since = program.since(today).and_time(NaiveTime::MIN).and_utc();
+
+            let mut tx = self.pool.begin().await?;
+            apply_rls_context(&mut tx, SYSTEM_USER_ID, SYSTEM_ROLE).await?;
+            let not_due: HashSet<Uuid> = sqlx::query_scalar(
+                r#"
+                SELECT patient_id FROM notification_queue
+                WHERE notification_type = 'SCREENING_REMINDER'
+                  AND patient_id = ANY($1)
+                  AND metadata->>'screening_program' = $2
+                  AND status IN ('SENT', 'PENDING', 'PROCESSING')
+                  AND created_at >= $3
+                UNION
+                SELECT patient_id FROM imaging_orders
+                WHERE patient_id = ANY($1)
+                  AND modality = $4
+                  AND deleted_at IS NULL
+                  AND status <> 'CANCELLED'
+                  AND ordered_at >= $3
+                UNION
+                SELECT o.patient_id
+                FROM lab_orders o
+                JOIN lab_order_tests t ON t.order_id = o.id
+                WHERE o.patient_id = ANY($1)
+                  AND o.deleted_at IS NULL
+                  AND o.status <> 'CANCELLED'
+                  AND t.loinc_code = ANY($5)
+                  AND o.ordered_at >= $3
+                UNION
+                SELECT patient_id FROM lab_results
+                WHERE patient_id = ANY($1)
+                  AND deleted_at IS NULL
+                  AND loinc_code = ANY($5)
+                  AND collected_at >= $3
+                "#,
+            )
+            .bind(&patient_ids)
+            .bind(&program.code)
+            .bind(since)
+            .bind(program.imaging_modality.map(|modality| modality.as_str()))
+            .bind(&program.lab_loinc_codes)
+            .fetch_all(&mut *tx)
+            .await?
+            .into_iter()
+            .collect();
+            tx.commit().await?;
+
+            for recipient in candidates {
+                if created >= limit {
+                    break;
+                }
+                if not_due.contains(&recipient.patient_id) {
+                    continue;
+                }
+
+                match self
+                    .notification_service
+                    .queue_screening_reminder(
+                        recipient.patient_id,
+                        &recipient.email,
+                        &recipient.name,
+                        &program.code,
+                        &program.name,
+                        SYSTEM_USER_ID,
+                    )
+                    .await
+                {
+                    Ok(_) => created += 1,
+                    Err(e) => warn!(
+                        "Failed to queue {} screening reminder for patient {}: {}",
+                        program.code, recipient.patient_id, e
+                    ),
+                }
+            }
+        }
+
+        Ok(created)
+    }
+
+    /// Queue a VISIT_SURVEY to patients after a completed appointment
+    ///
+    /// Appointments completed in the last `VISIT_SURVEY_LOOKBACK_DAYS` get
+    /// a survey, at most one per patient every
+    /// `VISIT_SURVEY_PATIENT_INTERVAL_DAYS`, to patients who accept email.
+    /// The survey row and its email are created together. The survey link
+    /// needs the practice's public URL; without one nothing is sent.
+    async fn generate_visit_surveys(&self, limit: i64) -> Result<i64> {
+        let Some(public_base_url) = BrandingService::new(self.pool.clone())
+            .load_settings()
+            .await
+            .ok()
+            .and_then(|s| s.public_base_url)
+            .filter(|url| !url.trim().is_empty())
+        else {
+            warn!("Visit surveys are enabled but no public URL is set, skipping");
+            return Ok(0);
+        };
+
+        let mut created = 0;
+
+        let mut tx = self.pool.begin().await?;
+        apply_rls_context(&mut tx, SYSTEM_USER_ID, SYSTEM_ROLE).await?;
+
+        // The most recent completed appointment of each patient
+        #[allow(clippy::type_complexity)]
+        let appointments: Vec<(
+            Uuid,
+            Uuid,
+            Uuid,
+            DateTime<Utc>,
+            String,
+            String,
+            Option<String>,
+            String,
+            String,
+        )> = sqlx::query_as(
+            r#"
+            SELECT DISTINCT ON (a.patient_id)
+                   a.id, a.patient_id, a.provider_id, a.scheduled_start,
+                   p.first_name, p.last_name, COALESCE(pnp.email_address_override, p.email),
+                   u.first_name, u.last_name
+            FROM appointments a
+            INNER JOIN patients p ON p.id = a.patient_id
+            INNER JOIN users u ON u.id = a.provider_id
+            LEFT JOIN patient_notification_preferences pnp ON pnp.patient_id = a.patient_id
+            WHERE a.status = 'COMPLETED'
+              AND a.scheduled_end <= NOW()
+              AND a.scheduled_end > NOW() - make_interval(days => $1::INTEGER)
+              AND p.status = 'ACTIVE'
+              AND p.deleted_at IS NULL
+              AND p.merged_at IS NULL
+              AND p.anonymized_at IS NULL
+              AND (pnp.email_enabled IS NULL OR pnp.email_enabled = true)
+              AND NOT EXISTS (
+                  SELECT 1 FROM visit_surveys vs
+                  WHERE vs.appointment_id = a.id
+                     OR (vs.patient_id = a.patient_id
+                         AND vs.sent_at > NOW() - make_interval(days => $2::INTEGER))
+              )
+            ORDER BY a.patient_id, a.scheduled_end DESC
+            LIMIT $3
+            "#,
+        )
+        .bind(VISIT_SURVEY_LOOKBACK_DAYS as i32)
+        .bind(VISIT_SURVEY_PATIENT_INTERVAL_DAYS as i32)
+        .bind(limit)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        for (
+            appointment_id,
+            patient_id,
+            provider_id,
+            scheduled_start,
+            patient_first_name,
+            patient_last_name,
+            patient_email,
+            provider_first_name,
+            provider_last_name,
+        ) in appointments
+        {
+            let Some(patient_email) = patient_email else {
+                debug!(
+                    "Skipping survey for appointment {} - no email address",
+                    appointment_id
+                );
+                continue;
+            };
+            let decrypted = (
+                self.encryption_key.decrypt(&patient_first_name),
+                self.encryption_key.decrypt(&patient_last_name),
+                self.encryption_key.decrypt(&patient_email),
+            );
+            let (Ok(first_name), Ok(last_name), Ok(recipient_email)) = decrypted else {
+                warn!(
+                    "Failed to decrypt patient {} for appointment {}, skipping survey",
+                    patient_id, appointment_id
+                );
+                continue;
+            };
+            if recipient_email.trim().is_empty() {
+                continue;
+            }
+            let patient_name = format!("{} {}", first_name, last_name);
+            let provider_name = format!("Dr. {} {}", provider_first_name, provider_last_name);
+
+            let survey_id = Uuid::new_v4();
+            let notification_id = Uuid::new_v4();
+            let expires_at = Utc::now() + Duration::days(VISIT_SURVEY_VALID_DAYS);
+            let survey_url = NotificationService::visit_survey_url(
+                &self.encryption_key,
+                &public_base_url,
+                survey_id,
+                patient_id,
+                expires_at,
+            );
+
+            let (subject, body) =
+                crate::services::notification_service::generate_visit_survey_email(
+                    &patient_name,
+                    &scheduled_start,
+                    &provider_name,
+                    &survey_url,
+                    VISIT_SURVEY_VALID_DAYS,
+                );
+
+            sqlx::query(
+                r#"
+                INSERT INTO notification_queue (
+                    id, patient_id, appointment_id, notification_type, delivery_method,
+                    recipient_email, recipient_name, subject, message_body,
+                    scheduled_for, priority, status, metadata, created_by
+                )
+                VALUES ($1, $2, $3, 'VISIT_SURVEY', 'EMAIL', $4, $5, $6, $7, NOW(), 3, 'PENDING', $8, $9)
+                "#,
+            )
+            .bind(notification_id)
+            .bind(patient_id)
+            .bind(appointment_id)
+            .bind(&recipient_email)
+            .bind(&patient_name)
+            .bind(&subject)
+            .bind(&body)
+            .bind(serde_json::json!({ "survey_id": survey_id }))
+            .bind(SYSTEM_USER_ID)
+            .execute(&mut *tx)
+            .await?;
+
+            sqlx::query(
+                r#"
+                INSERT INTO visit_surveys (
+                    id, appointment_id, patient_id, provider_id, notification_id, expires_at
+                )
+                VALUES ($1, $2, $3, $4, $5, $6)
+                "#,
+            )
+            .bind(survey_id)
+            .bind(appointment_id)
+            .bind(patient_id)
+            .bind(provider_id)
+            .bind(notification_id)
+            .bind(expires_at)
+            .execute(&mut *tx)
+            .await?;
+
+            info!(
+                "Created survey for appointment {} (patient: {})",
+                appointment_id, patient_id
+            );
+            created += 1;
+        }
+
+        tx.commit().await?;
+
+        Ok(created)
+    }
+
     /// Process pending notifications
     async fn process_pending_notifications(&self, limit: i64) -> Result<i64> {
         let pending = self
@@ -520,6 +1235,9 @@ pub fn spawn_notification_scheduler(
     settings_service: Arc<SettingsService>,
     encryption_key: EncryptionKey,
 ) {
+    // Keep this worker's notification leases alive while it processes the queue
+    notification_service.spawn_heartbeat(SYSTEM_USER_ID);
+
     let scheduler = Arc::new(NotificationScheduler::new(
         pool,
         notification_service,
@@ -578,6 +1296,12 @@ mod tests {
         assert_eq!(config.reminder_time, "08:00");
         assert_eq!(config.batch_size, 50);
         assert!(config.retry_failed_enabled);
+        assert_eq!(config.referral_reminder_interval_days, 7);
+        assert!(!config.birthday_greetings_enabled);
+        assert_eq!(config.birthday_greeting_message, DEFAULT_BIRTHDAY_GREETING);
+        assert!(!config.screening_reminders_enabled);
+        assert!(config.screening_programs.is_empty());
+        assert!(!config.visit_surveys_enabled);
     }
 
     #[test]
@@ -684,6 +1408,12 @@ mod tests {
             reminder_time: "09:30".to_string(),
             batch_size: 100,
             retry_failed_enabled: false,
+            referral_reminder_interval_days: 14,
+            birthday_greetings_enabled: true,
+            birthday_greeting_message: "Happy birthday!".to_string(),
+            screening_reminders_enabled: true,
+            screening_programs: Vec::new(),
+            visit_surveys_enabled: true,
         };
 
         let cloned = config.clone();
@@ -7,23 +7,116 @@
  * - Retry logic for failed notifications
  * - Patient notification preferences management
  * - Notification history queries
+ * - Marketing consent checks (at queueing and again before sending)
  */
 
 use crate::{
+    db::rls::begin_with_rls,
+    models::request_context::with_request_id_scope,
     models::{
-        CreateNotificationRequest, ListNotificationsResponse, Notification, NotificationFilter,
-        NotificationResponse, NotificationStatistics, PatientNotificationPreferences,
-        PatientNotificationPreferencesResponse, UpdateNotificationPreferencesRequest,
+        AppointmentLinkAction, AppointmentLinkClaims, ConsentType, CreateNotificationRequest, ListNotificationsResponse, Notification,
+        NotificationFilter, NotificationResponse, NotificationStatistics, NotificationType,
+        PatientNotificationPreferences, PatientNotificationPreferencesResponse,
+        TelevisitClaims, UpdateNotificationPreferencesRequest, VisitSurveyClaims,
+        WaitlistOfferAction, WaitlistOfferClaims, TELEVISIT_JOIN_EARLY_MINUTES,
     },
     services::email_service::{EmailResult, EmailService},
+    services::{BrandingService, PatientCommunicationService, PatientConsentService},
 };
+use crate::utils::encryption::EncryptionKey;
 use anyhow::{Context, Result};
-use chrono::{Duration, Utc};
+use chrono::{DateTime, Duration, Utc};
 use chrono_tz::Europe::Rome;
-use sqlx::{PgPool, Postgres, Transaction};
-use tracing::{debug, error, info, warn};
+use sqlx::PgPool;
+use std::sync::OnceLock;
+use tracing::{debug, error, info, info_span, warn, Instrument};
 use uuid::Uuid;
 
+/// Processing lease granted to a worker when it claims a notification
+pub(crate) const LEASE_DURATION_SECS: u64 = 300;
+
+/// How often a worker renews its leases (well inside the lease duration)
+const HEARTBEAT_INTERVAL_SECS: u64 = 60;
+
+/// Columns returned when claiming notifications (matches `Notification`)
+const CLAIM_RETURNING_COLUMNS: &str = "nq.id, nq.patient_id, nq.appointment_id, nq.user_id, \
+    nq.notification_type, nq.delivery_method, nq.recipient_email, nq.recipient_phone, \
+    nq.recipient_name, nq.subject, nq.message_body, nq.message_template, nq.scheduled_for, \
+    nq.priority, nq.status, nq.retry_count, nq.max_retries, nq.last_retry_at, nq.next_retry_at, \
+    nq.sent_at, nq.delivered_at, nq.delivery_status, nq.delivery_receipt, nq.error_message, \
+    nq.error_code, nq.provider_name, nq.provider_message_id, nq.metadata, nq.created_at, \
+    nq.updated_at, nq.created_by";
+
+/// Signing purpose of appointment link tokens
+const APPOINTMENT_LINK_PURPOSE: &str = "appointment-link";
+
+/// Frontend route that handles appointment links
+const APPOINTMENT_LINK_PATH: &str = "/appointments/respond";
+
+/// Signing purpose of visit survey tokens
+const VISIT_SURVEY_PURPOSE: &str = "visit-survey";
+
+/// Frontend route that handles visit survey links
+const VISIT_SURVEY_PATH: &str = "/surveys/respond";
+
+/// Signing purpose of televisit tokens
+const TELEVISIT_PURPOSE: &str = "televisit";
+
+/// Frontend route that handles televisit links
+const TELEVISIT_PATH: &str = "/televisit/join";
+
+/// Signing purpose of waitlist offer tokens
+const WAITLIST_OFFER_PURPOSE: &str = "waitlist-offer";
+
+/// Frontend route that handles waitlist offer links
+const WAITLIST_OFFER_PATH: &str = "/waitlist/respond";
+
+static WORKER_ID: OnceLock<String> = OnceLock::new();
+
+/// Identifier of this worker process, used as the lease owner
+///
+/// Defaults to `<hostname>-<pid>-<random>`; can be pinned with
+/// `NOTIFICATION_WORKER_ID` (e.g. to the pod name).
+pub fn worker_id() -> &'static str {
+    WORKER_ID.get_or_init(|| {
+        std::env::var("NOTIFICATION_WORKER_ID")
+            .ok()
+            .filter(|id| !id.trim().is_empty())
+            .unwrap_or_else(|| {
+                let host = hostname::get()
+                    .ok()
+                    .and_then(|h| h.into_string().ok())
+                    .unwrap_or_else(|| "docpat".to_string());
+                let suffix = Uuid::new_v4().simple().to_string();
+                format!("{}-{}-{}", host, std::process::id(), &suffix[..8])
+            })
+    })
+}
+
+/// UPDATE ... RETURNING does not preserve the claim order; restore it
+fn sort_claimed(mut notifications: Vec<Notification>) -> Vec<Notification> {
+    notifications.sort_by(|a, b| {
+        a.priority
+            .cmp(&b.priority)
+            .then(a.scheduled_for.cmp(&b.scheduled_for))
+    });
+    notifications
+}
+
+/// Confirm and cancel links of an appointment reminder
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppointmentResponseLinks {
+    pub confirm_url: String,
+    pub cancel_url: String,
+}
+
+/// Accept and decline links of a waitlist offer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WaitlistOfferLinks {
+    pub accept_url: String,
+    pub decline_url: String,
+}
+
 /// Notification Service
 #[derive(Clone)]
 pub struct NotificationService {
@@ -40,40 +133,6 @@ impl NotificationService {
         }
     }
 
-    /// Helper to set RLS context in a transaction
-    ///
-    /// This sets the PostgreSQL session variables required by Row-Level Security policies.
-    async fn set_rls_context(
-        tx: &mut Transaction<'_, Postgres>,
-        user_id: Uuid,
-    ) -> Result<()> {
-        // Query the user's role from the database
-        let role: String = sqlx::query_scalar(
-            "SELECT role::TEXT FROM users WHERE id = $1"
-        )
-        .bind(user_id)
-        .fetch_one(&mut **tx)
-        .await
-        .context("Failed to fetch user role for RLS context")?;
-
-        debug!("Setting RLS context: user_id={}, role={}", user_id, role);
-
-        // Set RLS context variables using set_config() for parameterized queries
-        sqlx::query("SELECT set_config('app.current_user_id', $1, true)")
-            .bind(user_id.to_string())
-            .execute(&mut **tx)
-            .await
-            .context("Failed to set RLS user context")?;
-
-        sqlx::query("SELECT set_config('app.current_user_role', $1, true)")
-            .bind(&role)
-            .execute(&mut **tx)
-            .await
-            .context("Failed to set RLS role context")?;
-
-        Ok(())
-    }
-
     // ========================================================================
     // NOTIFICATION CRUD
     // ========================================================================
@@ -91,9 +150,20 @@ impl NotificationService {
             .map(|m| serde_json::to_value(m).ok())
             .flatten();
 
-        // Start transaction and set RLS context for INSERT
-        let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-        Self::set_rls_context(&mut tx, created_by).await?;
+        if data.notification_type == NotificationType::Marketing.as_str() {
+            let patient_id = data
+                .patient_id
+                .ok_or_else(|| anyhow::anyhow!("Marketing notifications require a patient_id"))?;
+            if !self.has_marketing_consent(patient_id, created_by).await? {
+                return Err(anyhow::anyhow!(
+                    "Patient has no active MARKETING consent"
+                ));
+            }
+        }
+
+        let mut tx = begin_with_rls(&self.pool, created_by)
+            .await
+            .context("Failed to begin transaction")?;
 
         let notification = sqlx::query_as!(
             Notification,
@@ -144,8 +214,9 @@ impl NotificationService {
 
     /// Get notification by ID (requires user_id for RLS)
     pub async fn get_notification(&self, id: Uuid, user_id: Uuid) -> Result<Option<NotificationResponse>> {
-        let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-        Self::set_rls_context(&mut tx, user_id).await?;
+        let mut tx = begin_with_rls(&self.pool, user_id)
+            .await
+            .context("Failed to begin transaction")?;
 
         let notification = sqlx::query_as!(
             Notification,
@@ -191,9 +262,9 @@ impl NotificationService {
         let limit = filter.limit.unwrap_or(50).min(100);
         let offset = filter.offset.unwrap_or(0);
 
-        // Start transaction and set RLS context for SELECT
-        let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-        Self::set_rls_context(&mut tx, user_id).await?;
+        let mut tx = begin_with_rls(&self.pool, user_id)
+            .await
+            .context("Failed to begin transaction")?;
 
         // Use simpler query with optional bindings
         let notifications = sqlx::query_as!(
@@ -278,82 +349,210 @@ impl NotificationService {
         })
     }
 
-    /// Get pending notifications ready to send (requires user_id for RLS)
+    /// Claim pending notifications ready to send (requires user_id for RLS)
+    ///
+    /// Rows are locked with `FOR UPDATE SKIP LOCKED` and moved to PROCESSING under
+    /// this worker's lease, so concurrent workers never receive the same row.
+    /// PROCESSING rows whose lease has expired (worker crashed mid-send) are
+    /// reclaimed as well.
     pub async fn get_pending_notifications(&self, limit: i64, user_id: Uuid) -> Result<Vec<Notification>> {
-        let now = Utc::now();
-
-        let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-        Self::set_rls_context(&mut tx, user_id).await?;
+        let mut tx = begin_with_rls(&self.pool, user_id)
+            .await
+            .context("Failed to begin transaction")?;
 
-        let notifications = sqlx::query_as!(
-            Notification,
+        let notifications = sqlx::query_as::<_, Notification>(&format!(
             r#"
-            SELECT
-                id, patient_id, appointment_id, user_id, notification_type,
-                delivery_method, recipient_email, recipient_phone, recipient_name,
-                subject, message_body, message_template, scheduled_for, priority,
-                status, retry_count, max_retries, last_retry_at, next_retry_at,
-                sent_at, delivered_at, delivery_status, delivery_receipt,
-                error_message, error_code, provider_name, provider_message_id,
-                metadata, created_at, updated_at, created_by
-            FROM notification_queue
-            WHERE status = 'PENDING'
-              AND scheduled_for <= $1
-            ORDER BY priority ASC, scheduled_for ASC
-            LIMIT $2
+            WITH claimable AS (
+                SELECT id
+                FROM notification_queue
+                WHERE (status = 'PENDING' AND scheduled_for <= NOW())
+                   OR (status = 'PROCESSING'
+                       AND (lease_expires_at IS NULL OR lease_expires_at < NOW()))
+                ORDER BY priority ASC, scheduled_for ASC
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            UPDATE notification_queue nq
+            SET status = 'PROCESSING',
+                claimed_by = $2,
+                claimed_at = NOW(),
+                lease_expires_at = NOW() + make_interval(secs => $3)
+            FROM claimable
+            WHERE nq.id = claimable.id
+            RETURNING {}
             "#,
-            now,
-            limit
-        )
+            CLAIM_RETURNING_COLUMNS
+        ))
+        .bind(limit)
+        .bind(worker_id())
+        .bind(LEASE_DURATION_SECS as f64)
         .fetch_all(&mut *tx)
         .await
-        .context("Failed to fetch pending notifications")?;
+        .context("Failed to claim pending notifications")?;
 
         tx.commit().await.context("Failed to commit transaction")?;
-        Ok(notifications)
+
+        if !notifications.is_empty() {
+            debug!("Worker {} claimed {} pending notifications", worker_id(), notifications.len());
+        }
+
+        Ok(sort_claimed(notifications))
     }
 
-    /// Get notifications ready for retry (requires user_id for RLS)
+    /// Claim failed notifications ready for retry (requires user_id for RLS)
+    ///
+    /// Same claiming semantics as `get_pending_notifications`; the FAILED →
+    /// PROCESSING transition increments retry_count via trigger.
     pub async fn get_retry_notifications(&self, limit: i64, user_id: Uuid) -> Result<Vec<Notification>> {
-        let now = Utc::now();
+        let mut tx = begin_with_rls(&self.pool, user_id)
+            .await
+            .context("Failed to begin transaction")?;
+
+        let notifications = sqlx::query_as::<_, Notification>(&format!(
+            r#"
+            WITH claimable AS (
+                SELECT id
+                FROM notification_queue
+                WHERE status = 'FAILED'
+                  AND retry_count < max_retries
+                  AND next_retry_at IS NOT NULL
+                  AND next_retry_at <= NOW()
+                ORDER BY priority ASC, next_retry_at ASC
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            UPDATE notification_queue nq
+            SET status = 'PROCESSING',
+                claimed_by = $2,
+                claimed_at = NOW(),
+                lease_expires_at = NOW() + make_interval(secs => $3)
+            FROM claimable
+            WHERE nq.id = claimable.id
+            RETURNING {}
+            "#,
+            CLAIM_RETURNING_COLUMNS
+        ))
+        .bind(limit)
+        .bind(worker_id())
+        .bind(LEASE_DURATION_SECS as f64)
+        .fetch_all(&mut *tx)
+        .await
+        .context("Failed to claim retry notifications")?;
 
-        let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-        Self::set_rls_context(&mut tx, user_id).await?;
+        tx.commit().await.context("Failed to commit transaction")?;
 
-        let notifications = sqlx::query_as!(
-            Notification,
+        if !notifications.is_empty() {
+            debug!("Worker {} claimed {} retry notifications", worker_id(), notifications.len());
+        }
+
+        Ok(sort_claimed(notifications))
+    }
+
+    // ========================================================================
+    // WORKER LEASES
+    // ========================================================================
+
+    /// Claim a single notification for immediate delivery
+    ///
+    /// Succeeds for PENDING rows and for rows this worker already holds.
+    /// Returns false when another worker owns the row (or it is no longer
+    /// sendable), in which case the caller must not send it.
+    async fn claim_notification(&self, id: Uuid, user_id: Uuid) -> Result<bool> {
+        let mut tx = begin_with_rls(&self.pool, user_id)
+            .await
+            .context("Failed to begin transaction")?;
+
+        let result = sqlx::query(
             r#"
-            SELECT
-                id, patient_id, appointment_id, user_id, notification_type,
-                delivery_method, recipient_email, recipient_phone, recipient_name,
-                subject, message_body, message_template, scheduled_for, priority,
-                status, retry_count, max_retries, last_retry_at, next_retry_at,
-                sent_at, delivered_at, delivery_status, delivery_receipt,
-                error_message, error_code, provider_name, provider_message_id,
-                metadata, created_at, updated_at, created_by
-            FROM notification_queue
-            WHERE status = 'FAILED'
-              AND retry_count < max_retries
-              AND next_retry_at IS NOT NULL
-              AND next_retry_at <= $1
-            ORDER BY priority ASC, next_retry_at ASC
-            LIMIT $2
+            UPDATE notification_queue
+            SET status = 'PROCESSING',
+                claimed_by = $2,
+                claimed_at = COALESCE(claimed_at, NOW()),
+                lease_expires_at = NOW() + make_interval(secs => $3)
+            WHERE id = $1
+              AND (status = 'PENDING' OR (status = 'PROCESSING' AND claimed_by = $2))
             "#,
-            now,
-            limit
         )
-        .fetch_all(&mut *tx)
+        .bind(id)
+        .bind(worker_id())
+        .bind(LEASE_DURATION_SECS as f64)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to claim notification")?;
+
+        tx.commit().await.context("Failed to commit transaction")?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// Record a worker heartbeat and extend the leases this worker holds
+    ///
+    /// Returns the number of PROCESSING notifications whose lease was extended.
+    pub async fn heartbeat(&self, user_id: Uuid) -> Result<u64> {
+        let hostname = hostname::get()
+            .ok()
+            .and_then(|h| h.into_string().ok());
+
+        sqlx::query(
+            r#"
+            INSERT INTO notification_workers (worker_id, hostname)
+            VALUES ($1, $2)
+            ON CONFLICT (worker_id) DO UPDATE SET last_heartbeat_at = NOW()
+            "#,
+        )
+        .bind(worker_id())
+        .bind(hostname)
+        .execute(&self.pool)
+        .await
+        .context("Failed to record worker heartbeat")?;
+
+        let mut tx = begin_with_rls(&self.pool, user_id)
+            .await
+            .context("Failed to begin transaction")?;
+
+        let extended = sqlx::query(
+            r#"
+            UPDATE notification_queue
+            SET lease_expires_at = NOW() + make_interval(secs => $2)
+            WHERE status = 'PROCESSING' AND claimed_by = $1
+            "#,
+        )
+        .bind(worker_id())
+        .bind(LEASE_DURATION_SECS as f64)
+        .execute(&mut *tx)
         .await
-        .context("Failed to fetch retry notifications")?;
+        .context("Failed to extend notification leases")?
+        .rows_affected();
 
         tx.commit().await.context("Failed to commit transaction")?;
-        Ok(notifications)
+        Ok(extended)
+    }
+
+    /// Spawn the periodic heartbeat for this worker process
+    pub fn spawn_heartbeat(&self, user_id: Uuid) {
+        let service = self.clone();
+        tokio::spawn(async move {
+            let mut interval =
+                tokio::time::interval(std::time::Duration::from_secs(HEARTBEAT_INTERVAL_SECS));
+            loop {
+                interval.tick().await;
+                match service.heartbeat(user_id).await {
+                    Ok(extended) if extended > 0 => {
+                        debug!("Worker {} extended {} notification leases", worker_id(), extended)
+                    }
+                    Ok(_) => {}
+                    Err(e) => warn!("Notification worker heartbeat failed: {}", e),
+                }
+            }
+        });
+
+        info!("Notification worker {} heartbeat started", worker_id());
     }
 
     /// Cancel a pending notification (requires user_id for RLS)
     pub async fn cancel_notification(&self, id: Uuid, user_id: Uuid) -> Result<NotificationResponse> {
-        let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-        Self::set_rls_context(&mut tx, user_id).await?;
+        let mut tx = begin_with_rls(&self.pool, user_id)
+            .await
+            .context("Failed to begin transaction")?;
 
         // First check if notification can be cancelled
         let existing = sqlx::query_scalar!(
@@ -406,8 +605,9 @@ impl NotificationService {
 
     /// Retry a failed notification (requires user_id for RLS)
     pub async fn retry_notification(&self, id: Uuid, user_id: Uuid) -> Result<NotificationResponse> {
-        let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-        Self::set_rls_context(&mut tx, user_id).await?;
+        let mut tx = begin_with_rls(&self.pool, user_id)
+            .await
+            .context("Failed to begin transaction")?;
 
         // Check if can retry
         let existing = sqlx::query!(
@@ -473,26 +673,85 @@ impl NotificationService {
 
     /// Process a single notification - send email
     /// Requires user_id for RLS context on status updates
+    ///
+    /// Runs under the correlation ID of the request that queued the notification
+    /// (when known), so scheduler logs can be traced back to that request.
     pub async fn process_notification(&self, notification: &Notification, user_id: Uuid) -> Result<EmailResult> {
+        let correlation_id = self
+            .get_correlation_id(notification.id, user_id)
+            .await
+            .unwrap_or_else(|e| {
+                warn!("Failed to load correlation ID for notification {}: {}", notification.id, e);
+                None
+            });
+
+        let span = info_span!(
+            "notification_job",
+            notification_id = %notification.id,
+            correlation_id = ?correlation_id,
+        );
+
+        let job = self.deliver_notification(notification, user_id).instrument(span);
+        match correlation_id {
+            Some(id) => with_request_id_scope(id, job).await,
+            None => job.await,
+        }
+    }
+
+    /// Correlation ID of the request that queued a notification
+    async fn get_correlation_id(&self, id: Uuid, user_id: Uuid) -> Result<Option<Uuid>> {
+        let mut tx = begin_with_rls(&self.pool, user_id)
+            .await
+            .context("Failed to begin transaction")?;
+
+        let correlation_id: Option<Uuid> = sqlx::query_scalar(
+            "SELECT correlation_id FROM notification_queue WHERE id = $1",
+        )
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await
+        .context("Failed to fetch notification correlation ID")?
+        .flatten();
+
+        tx.commit().await.context("Failed to commit transaction")?;
+        Ok(correlation_id)
+    }
+
+    /// Send a notification and record the outcome
+    async fn deliver_notification(&self, notification: &Notification, user_id: Uuid) -> Result<EmailResult> {
         debug!(
             "process_notification called: id={}, status={}, user_id={}",
             notification.id, notification.status, user_id
         );
 
-        // Mark as PROCESSING (with RLS context)
-        {
-            let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-            Self::set_rls_context(&mut tx, user_id).await?;
-            let result = sqlx::query!(
-                r#"UPDATE notification_queue SET status = 'PROCESSING' WHERE id = $1 AND status = 'PENDING'"#,
+        // Claim the row under this worker's lease; skip it if another worker owns it
+        if !self.claim_notification(notification.id, user_id).await? {
+            debug!(
+                "Notification {} is claimed by another worker or no longer pending, skipping",
                 notification.id
-            )
-            .execute(&mut *tx)
-            .await
-            .context("Failed to mark notification as processing")?;
-            debug!("UPDATE to PROCESSING affected {} rows for notification {}", result.rows_affected(), notification.id);
-            tx.commit().await.context("Failed to commit transaction")?;
-            debug!("Committed PROCESSING status for notification {}", notification.id);
+            );
+            return Ok(EmailResult {
+                success: false,
+                message: "Notification is being processed by another worker".to_string(),
+            });
+        }
+
+        // Consent may have been withdrawn or expired since the notification was queued
+        if notification.notification_type == NotificationType::Marketing.as_str() {
+            let consented = match notification.patient_id {
+                Some(patient_id) => self.has_marketing_consent(patient_id, user_id).await?,
+                None => false,
+            };
+            if !consented {
+                let error_msg = "Patient has no active MARKETING consent".to_string();
+                self.mark_notification_consent_missing(notification.id, &error_msg, user_id)
+                    .await?;
+                info!("Notification {} not sent: {}", notification.id, error_msg);
+                return Ok(EmailResult {
+                    success: false,
+                    message: error_msg,
+                });
+            }
         }
 
         // Only handle EMAIL for now
@@ -533,13 +792,20 @@ impl NotificationService {
             .clone()
             .unwrap_or_else(|| "Notification from DocPat".to_string());
 
+        // Practice branding for the HTML wrapper (defaults if unavailable)
+        let branding = BrandingService::new(self.pool.clone())
+            .email_branding()
+            .await
+            .unwrap_or_default();
+
         // Generate HTML body (simple wrapper)
         let html_body = format!(
             r#"<!DOCTYPE html>
 <html>
 <head><meta charset="utf-8"></head>
 <body style="font-family: Arial, sans-serif; line-height: 1.6; color: #333;">
-<div style="max-width: 600px; margin: 0 auto; padding: 20px;">
+<div style="max-width: 600px; margin: 0 auto; padding: 20px; border-top: 3px solid {};">
+{}
 {}
 <hr style="margin-top: 30px; border: none; border-top: 1px solid #ddd;">
 <p style="font-size: 11px; color: #666;">
@@ -548,6 +814,8 @@ This is an automated message from DocPat Medical Practice Management System.
 </div>
 </body>
 </html>"#,
+            branding.primary_color,
+            branding.header_image_html("DocPat"),
             notification.message_body.replace("\n", "<br>")
         );
 
@@ -567,6 +835,15 @@ This is an automated message from DocPat Medical Practice Management System.
             debug!("Email sent successfully, calling mark_notification_sent for {}", notification.id);
             self.mark_notification_sent(notification.id, user_id).await?;
             debug!("mark_notification_sent completed for {}", notification.id);
+            if let Err(e) =
+                PatientCommunicationService::record_notification(&self.pool, notification.id, user_id)
+                    .await
+            {
+                warn!(
+                    "Failed to log notification {} in the communication log: {}",
+                    notification.id, e
+                );
+            }
             info!(
                 "Notification {} sent successfully to {}",
                 notification.id, recipient_email
@@ -586,8 +863,9 @@ This is an automated message from DocPat Medical Practice Management System.
     /// Mark notification as sent (requires RLS context)
     async fn mark_notification_sent(&self, id: Uuid, user_id: Uuid) -> Result<()> {
         debug!("mark_notification_sent called for id={} user_id={}", id, user_id);
-        let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-        Self::set_rls_context(&mut tx, user_id).await?;
+        let mut tx = begin_with_rls(&self.pool, user_id)
+            .await
+            .context("Failed to begin transaction")?;
         debug!("RLS context set for mark_notification_sent");
 
         let result = sqlx::query!(
@@ -617,8 +895,9 @@ This is an automated message from DocPat Medical Practice Management System.
         error_code: Option<&str>,
         user_id: Uuid,
     ) -> Result<()> {
-        let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-        Self::set_rls_context(&mut tx, user_id).await?;
+        let mut tx = begin_with_rls(&self.pool, user_id)
+            .await
+            .context("Failed to begin transaction")?;
 
         sqlx::query!(
             r#"
@@ -640,6 +919,50 @@ This is an automated message from DocPat Medical Practice Management System.
         Ok(())
     }
 
+    /// Fail a notification for missing consent, without scheduling retries
+    async fn mark_notification_consent_missing(
+        &self,
+        id: Uuid,
+        error_message: &str,
+        user_id: Uuid,
+    ) -> Result<()> {
+        let mut tx = begin_with_rls(&self.pool, user_id)
+            .await
+            .context("Failed to begin transaction")?;
+
+        sqlx::query(
+            r#"
+            UPDATE notification_queue
+            SET status = 'FAILED',
+                error_message = $2,
+                error_code = 'CONSENT_MISSING',
+                retry_count = max_retries,
+                next_retry_at = NULL
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(error_message)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to mark notification as failed")?;
+
+        tx.commit().await.context("Failed to commit transaction")?;
+        Ok(())
+    }
+
+    /// Whether the patient has an active MARKETING consent
+    async fn has_marketing_consent(&self, patient_id: Uuid, user_id: Uuid) -> Result<bool> {
+        PatientConsentService::has_active_consent(
+            &self.pool,
+            patient_id,
+            ConsentType::Marketing,
+            user_id,
+        )
+        .await
+        .context("Failed to check marketing consent")
+    }
+
     /// Process all pending notifications (called by scheduler)
     /// Requires user_id for RLS context
     pub async fn process_pending_notifications(&self, batch_size: i64, user_id: Uuid) -> Result<(i32, i32)> {
@@ -690,9 +1013,9 @@ This is an automated message from DocPat Medical Practice Management System.
         patient_id: Uuid,
         user_id: Uuid,
     ) -> Result<PatientNotificationPreferencesResponse> {
-        // Start transaction and set RLS context for SELECT
-        let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-        Self::set_rls_context(&mut tx, user_id).await?;
+        let mut tx = begin_with_rls(&self.pool, user_id)
+            .await
+            .context("Failed to begin transaction")?;
 
         // Security: First verify the patient exists (RLS will also check access)
         let patient_exists = sqlx::query_scalar!(
@@ -767,9 +1090,9 @@ This is an automated message from DocPat Medical Practice Management System.
         data: UpdateNotificationPreferencesRequest,
         updated_by: Uuid,
     ) -> Result<PatientNotificationPreferencesResponse> {
-        // Start transaction and set RLS context for INSERT/UPDATE
-        let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-        Self::set_rls_context(&mut tx, updated_by).await?;
+        let mut tx = begin_with_rls(&self.pool, updated_by)
+            .await
+            .context("Failed to begin transaction")?;
 
         // Security: First verify the patient exists (RLS will also check access)
         let patient_exists = sqlx::query_scalar!(
@@ -837,9 +1160,9 @@ This is an automated message from DocPat Medical Practice Management System.
         let today_start = Utc::now().date_naive().and_hms_opt(0, 0, 0).unwrap();
         let today_start = chrono::DateTime::<Utc>::from_naive_utc_and_offset(today_start, Utc);
 
-        // Start transaction and set RLS context for SELECT
-        let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-        Self::set_rls_context(&mut tx, user_id).await?;
+        let mut tx = begin_with_rls(&self.pool, user_id)
+            .await
+            .context("Failed to begin transaction")?;
 
         let stats = sqlx::query!(
             r#"
@@ -869,65 +1192,348 @@ This is an automated message from DocPat Medical Practice Management System.
     // APPOINTMENT NOTIFICATION HELPERS
     // ========================================================================
 
-    /// Queue appointment reminder notification
-    pub async fn queue_appointment_reminder(
-        &self,
+    /// Signed token of an appointment link, valid until `expires_at`
+    ///
+    /// Format: `<appointment>.<patient>.<action>.<expiry>.<signature>`, with
+    /// the ids in simple form and the expiry as a Unix timestamp.
+    pub fn generate_appointment_link_token(
+        key: &EncryptionKey,
+        appointment_id: Uuid,
         patient_id: Uuid,
+        action: AppointmentLinkAction,
+        expires_at: DateTime<Utc>,
+    ) -> String {
+        let payload = format!(
+            "{}.{}.{}.{}",
+            appointment_id.simple(),
+            patient_id.simple(),
+            action.as_str(),
+            expires_at.timestamp()
+        );
+        let signature = key.sign(APPOINTMENT_LINK_PURPOSE, &payload);
+        format!("{}.{}", payload, signature)
+    }
+
+    /// Contents of an appointment link token; `None` when it is malformed,
+    /// not signed with this key or expired at `now`
+    pub fn verify_appointment_link_token(
+        key: &EncryptionKey,
+        token: &str,
+        now: DateTime<Utc>,
+    ) -> Option<AppointmentLinkClaims> {
+        let (payload, signature) = token.trim().rsplit_once('.')?;
+        if !key.verify_signature(APPOINTMENT_LINK_PURPOSE, payload, signature) {
+            return None;
+        }
+
+        let mut parts = payload.split('.');
+        let appointment_id = Uuid::parse_str(parts.next()?).ok()?;
+        let patient_id = Uuid::parse_str(parts.next()?).ok()?;
+        let action = AppointmentLinkAction::from_str(parts.next()?)?;
+        let expires_at = DateTime::<Utc>::from_timestamp(parts.next()?.parse().ok()?, 0)?;
+        if parts.next().is_some() || expires_at <= now {
+            return None;
+        }
+
+        Some(AppointmentLinkClaims {
+            appointment_id,
+            patient_id,
+            action,
+            expires_at,
+        })
+    }
+
+    /// Confirm and cancel links for a reminder, valid until the appointment
+    /// starts
+    pub fn appointment_response_links(
+        key: &EncryptionKey,
+        base_url: &str,
         appointment_id: Uuid,
-        patient_email: &str,
-        patient_name: &str,
-        appointment_date: chrono::DateTime<Utc>,
-        doctor_name: &str,
-        appointment_type: &str,
-        days_before: i32,
-        created_by: Uuid,
-    ) -> Result<NotificationResponse> {
-        let scheduled_for = appointment_date - Duration::days(days_before as i64);
+        patient_id: Uuid,
+        scheduled_start: DateTime<Utc>,
+    ) -> AppointmentResponseLinks {
+        let url = |action| {
+            format!(
+                "{}{}?token={}",
+                base_url.trim().trim_end_matches('/'),
+                APPOINTMENT_LINK_PATH,
+                Self::generate_appointment_link_token(
+                    key,
+                    appointment_id,
+                    patient_id,
+                    action,
+                    scheduled_start,
+                )
+            )
+        };
 
-        // Don't queue if scheduled time is in the past
-        if scheduled_for <= Utc::now() {
-            return Err(anyhow::anyhow!(
-                "Reminder scheduled time is in the past"
-            ));
+        AppointmentResponseLinks {
+            confirm_url: url(AppointmentLinkAction::Confirm),
+            cancel_url: url(AppointmentLinkAction::Cancel),
         }
+    }
 
-        let (subject, body) = generate_appointment_reminder_email(
-            patient_name,
-            &appointment_date,
-            doctor_name,
-            appointment_type,
+    /// Signed token of a visit survey link, valid until `expires_at`
+    ///
+    /// Format: `<survey>.<patient>.<expiry>.<signature>`, with the ids in
+    /// simple form and the expiry as a Unix timestamp.
+    pub fn generate_visit_survey_token(
+        key: &EncryptionKey,
+        survey_id: Uuid,
+        patient_id: Uuid,
+        expires_at: DateTime<Utc>,
+    ) -> String {
+        let payload = format!(
+            "{}.{}.{}",
+            survey_id.simple(),
+            patient_id.simple(),
+            expires_at.timestamp()
         );
+        let signature = key.sign(VISIT_SURVEY_PURPOSE, &payload);
+        format!("{}.{}", payload, signature)
+    }
 
-        // Create metadata with appointment info for display in notification cards
-        let local_date = appointment_date.with_timezone(&Rome);
-        let metadata = serde_json::json!({
-            "appointment_date": local_date.format("%Y-%m-%d").to_string(),
-            "appointment_time": local_date.format("%H:%M").to_string(),
-            "appointment_type": appointment_type.to_string()
-        });
+    /// Contents of a visit survey token; `None` when it is malformed, not
+    /// signed with this key or expired at `now`
+    pub fn verify_visit_survey_token(
+        key: &EncryptionKey,
+        token: &str,
+        now: DateTime<Utc>,
+    ) -> Option<VisitSurveyClaims> {
+        let (payload, signature) = token.trim().rsplit_once('.')?;
+        if !key.verify_signature(VISIT_SURVEY_PURPOSE, payload, signature) {
+            return None;
+        }
 
-        let request = CreateNotificationRequest {
-            patient_id: Some(patient_id),
-            appointment_id: Some(appointment_id),
-            notification_type: "APPOINTMENT_REMINDER".to_string(),
-            delivery_method: "EMAIL".to_string(),
-            recipient_email: Some(patient_email.to_string()),
-            recipient_name: Some(patient_name.to_string()),
-            subject: Some(subject),
-            message_body: body,
-            scheduled_for: Some(scheduled_for),
-            priority: Some(3), // Higher priority for reminders
-            metadata: Some(metadata),
-        };
+        let mut parts = payload.split('.');
+        let survey_id = Uuid::parse_str(parts.next()?).ok()?;
+        let patient_id = Uuid::parse_str(parts.next()?).ok()?;
+        let expires_at = DateTime::<Utc>::from_timestamp(parts.next()?.parse().ok()?, 0)?;
+        if parts.next().is_some() || expires_at <= now {
+            return None;
+        }
 
-        self.create_notification(request, created_by).await
+        Some(VisitSurveyClaims {
+            survey_id,
+            patient_id,
+            expires_at,
+        })
     }
 
-    /// Queue and immediately send appointment booked notification (when created/scheduled)
-    pub async fn queue_appointment_booked(
-        &self,
+    /// Link to a visit survey, valid until `expires_at`
+    pub fn visit_survey_url(
+        key: &EncryptionKey,
+        base_url: &str,
+        survey_id: Uuid,
         patient_id: Uuid,
-        appointment_id: Uuid,
+        expires_at: DateTime<Utc>,
+    ) -> String {
+        format!(
+            "{}{}?token={}",
+            base_url.trim().trim_end_matches('/'),
+            VISIT_SURVEY_PATH,
+            Self::generate_visit_survey_token(key, survey_id, patient_id, expires_at)
+        )
+    }
+
+    /// Signed token of a televisit link, valid until `expires_at`
+    ///
+    /// Format: `<appointment>.<patient>.<expiry>.<signature>`, with the ids
+    /// in simple form and the expiry as a Unix timestamp.
+    pub fn generate_televisit_token(
+        key: &EncryptionKey,
+        appointment_id: Uuid,
+        patient_id: Uuid,
+        expires_at: DateTime<Utc>,
+    ) -> String {
+        let payload = format!(
+            "{}.{}.{}",
+            appointment_id.simple(),
+            patient_id.simple(),
+            expires_at.timestamp()
+        );
+        let signature = key.sign(TELEVISIT_PURPOSE, &payload);
+        format!("{}.{}", payload, signature)
+    }
+
+    /// Contents of a televisit token; `None` when it is malformed, not
+    /// signed with this key or expired at `now`
+    pub fn verify_televisit_token(
+        key: &EncryptionKey,
+        token: &str,
+        now: DateTime<Utc>,
+    ) -> Option<TelevisitClaims> {
+        let (payload, signature) = token.trim().rsplit_once('.')?;
+        if !key.verify_signature(TELEVISIT_PURPOSE, payload, signature) {
+            return None;
+        }
+
+        let mut parts = payload.split('.');
+        let appointment_id = Uuid::parse_str(parts.next()?).ok()?;
+        let patient_id = Uuid::parse_str(parts.next()?).ok()?;
+        let expires_at = DateTime::<Utc>::from_timestamp(parts.next()?.parse().ok()?, 0)?;
+        if parts.next().is_some() || expires_at <= now {
+            return None;
+        }
+
+        Some(TelevisitClaims {
+            appointment_id,
+            patient_id,
+            expires_at,
+        })
+    }
+
+    /// Link to join a televisit, valid until `expires_at`
+    pub fn televisit_url(
+        key: &EncryptionKey,
+        base_url: &str,
+        appointment_id: Uuid,
+        patient_id: Uuid,
+        expires_at: DateTime<Utc>,
+    ) -> String {
+        format!(
+            "{}{}?token={}",
+            base_url.trim().trim_end_matches('/'),
+            TELEVISIT_PATH,
+            Self::generate_televisit_token(key, appointment_id, patient_id, expires_at)
+        )
+    }
+
+    /// Signed token of a waitlist offer link, valid until `expires_at`
+    ///
+    /// Format: `<offer>.<patient>.<action>.<expiry>.<signature>`, with the
+    /// ids in simple form and the expiry as a Unix timestamp.
+    pub fn generate_waitlist_offer_token(
+        key: &EncryptionKey,
+        offer_id: Uuid,
+        patient_id: Uuid,
+        action: WaitlistOfferAction,
+        expires_at: DateTime<Utc>,
+    ) -> String {
+        let payload = format!(
+            "{}.{}.{}.{}",
+            offer_id.simple(),
+            patient_id.simple(),
+            action.as_str(),
+            expires_at.timestamp()
+        );
+        let signature = key.sign(WAITLIST_OFFER_PURPOSE, &payload);
+        format!("{}.{}", payload, signature)
+    }
+
+    /// Contents of a waitlist offer token; `None` when it is malformed, not
+    /// signed with this key or expired at `now`
+    pub fn verify_waitlist_offer_token(
+        key: &EncryptionKey,
+        token: &str,
+        now: DateTime<Utc>,
+    ) -> Option<WaitlistOfferClaims> {
+        let (payload, signature) = token.trim().rsplit_once('.')?;
+        if !key.verify_signature(WAITLIST_OFFER_PURPOSE, payload, signature) {
+            return None;
+        }
+
+        let mut parts = payload.split('.');
+        let offer_id = Uuid::parse_str(parts.next()?).ok()?;
+        let patient_id = Uuid::parse_str(parts.next()?).ok()?;
+        let action = WaitlistOfferAction::from_str(parts.next()?)?;
+        let expires_at = DateTime::<Utc>::from_timestamp(parts.next()?.parse().ok()?, 0)?;
+        if parts.next().is_some() || expires_at <= now {
+            return None;
+        }
+
+        Some(WaitlistOfferClaims {
+            offer_id,
+            patient_id,
+            action,
+            expires_at,
+        })
+    }
+
+    /// Accept and decline links of a waitlist offer, valid until the offer
+    /// expires
+    pub fn waitlist_offer_links(
+        key: &EncryptionKey,
+        base_url: &str,
+        offer_id: Uuid,
+        patient_id: Uuid,
+        expires_at: DateTime<Utc>,
+    ) -> WaitlistOfferLinks {
+        let url = |action| {
+            format!(
+                "{}{}?token={}",
+                base_url.trim().trim_end_matches('/'),
+                WAITLIST_OFFER_PATH,
+                Self::generate_waitlist_offer_token(key, offer_id, patient_id, action, expires_at)
+            )
+        };
+
+        WaitlistOfferLinks {
+            accept_url: url(WaitlistOfferAction::Accept),
+            decline_url: url(WaitlistOfferAction::Decline),
+        }
+    }
+
+    /// Queue appointment reminder notification
+    pub async fn queue_appointment_reminder(
+        &self,
+        patient_id: Uuid,
+        appointment_id: Uuid,
+        patient_email: &str,
+        patient_name: &str,
+        appointment_date: chrono::DateTime<Utc>,
+        doctor_name: &str,
+        appointment_type: &str,
+        days_before: i32,
+        created_by: Uuid,
+    ) -> Result<NotificationResponse> {
+        let scheduled_for = appointment_date - Duration::days(days_before as i64);
+
+        // Don't queue if scheduled time is in the past
+        if scheduled_for <= Utc::now() {
+            return Err(anyhow::anyhow!(
+                "Reminder scheduled time is in the past"
+            ));
+        }
+
+        let (subject, body) = generate_appointment_reminder_email(
+            patient_name,
+            &appointment_date,
+            doctor_name,
+            appointment_type,
+            None,
+        );
+
+        // Create metadata with appointment info for display in notification cards
+        let local_date = appointment_date.with_timezone(&Rome);
+        let metadata = serde_json::json!({
+            "appointment_date": local_date.format("%Y-%m-%d").to_string(),
+            "appointment_time": local_date.format("%H:%M").to_string(),
+            "appointment_type": appointment_type.to_string()
+        });
+
+        let request = CreateNotificationRequest {
+            patient_id: Some(patient_id),
+            appointment_id: Some(appointment_id),
+            notification_type: "APPOINTMENT_REMINDER".to_string(),
+            delivery_method: "EMAIL".to_string(),
+            recipient_email: Some(patient_email.to_string()),
+            recipient_name: Some(patient_name.to_string()),
+            subject: Some(subject),
+            message_body: body,
+            scheduled_for: Some(scheduled_for),
+            priority: Some(3), // Higher priority for reminders
+            metadata: Some(metadata),
+        };
+
+        self.create_notification(request, created_by).await
+    }
+
+    /// Queue and immediately send appointment booked notification (when created/scheduled)
+    pub async fn queue_appointment_booked(
+        &self,
+        patient_id: Uuid,
+        appointment_id: Uuid,
         patient_email: &str,
         patient_name: &str,
         appointment_date: chrono::DateTime<Utc>,
@@ -1135,11 +1741,148 @@ This is an automated message from DocPat Medical Practice Management System.
         Ok(notification_response)
     }
 
+    /// Queue and immediately send the join link of a televisit
+    pub async fn queue_televisit_invitation(
+        &self,
+        patient_id: Uuid,
+        appointment_id: Uuid,
+        patient_email: &str,
+        patient_name: &str,
+        appointment_date: chrono::DateTime<Utc>,
+        doctor_name: &str,
+        join_link: &str,
+        created_by: Uuid,
+    ) -> Result<NotificationResponse> {
+        let (subject, body) =
+            generate_televisit_invitation_email(patient_name, &appointment_date, doctor_name, join_link);
+
+        // Create metadata with appointment info for display in notification cards
+        let local_date = appointment_date.with_timezone(&Rome);
+        let metadata = serde_json::json!({
+            "appointment_date": local_date.format("%Y-%m-%d").to_string(),
+            "appointment_time": local_date.format("%H:%M").to_string(),
+            "appointment_type": "TELEVISIT"
+        });
+
+        let request = CreateNotificationRequest {
+            patient_id: Some(patient_id),
+            appointment_id: Some(appointment_id),
+            notification_type: "TELEVISIT_INVITATION".to_string(),
+            delivery_method: "EMAIL".to_string(),
+            recipient_email: Some(patient_email.to_string()),
+            recipient_name: Some(patient_name.to_string()),
+            subject: Some(subject),
+            message_body: body,
+            scheduled_for: None, // Send immediately
+            priority: Some(2),
+            metadata: Some(metadata),
+        };
+
+        // Create notification record
+        let notification_response = self.create_notification(request, created_by).await?;
+
+        // Immediately send the notification (don't wait for scheduler)
+        match self.get_notification_by_id(notification_response.id, created_by).await {
+            Ok(Some(n)) => {
+                match self.process_notification(&n, created_by).await {
+                    Ok(result) if result.success => {
+                        info!("Televisit invitation sent immediately for appointment {}", appointment_id);
+                    }
+                    Ok(result) => {
+                        warn!("Televisit invitation failed for appointment {}: {}", appointment_id, result.message);
+                    }
+                    Err(e) => {
+                        warn!("Failed to process televisit invitation: {}", e);
+                    }
+                }
+            }
+            Ok(None) => {
+                warn!("Could not find notification {} for immediate send", notification_response.id);
+            }
+            Err(e) => {
+                warn!("Failed to fetch notification for immediate send: {}", e);
+            }
+        }
+
+        Ok(notification_response)
+    }
+
+    /// Queue and immediately send the offer of a freed slot to a waitlisted
+    /// patient
+    pub async fn queue_waitlist_offer(
+        &self,
+        patient_id: Uuid,
+        patient_email: &str,
+        patient_name: &str,
+        slot_start: chrono::DateTime<Utc>,
+        doctor_name: &str,
+        links: &WaitlistOfferLinks,
+        expires_at: chrono::DateTime<Utc>,
+        created_by: Uuid,
+    ) -> Result<NotificationResponse> {
+        let (subject, body) = generate_waitlist_offer_email(
+            patient_name,
+            &slot_start,
+            doctor_name,
+            links,
+            &expires_at,
+        );
+
+        // Create metadata with appointment info for display in notification cards
+        let local_date = slot_start.with_timezone(&Rome);
+        let metadata = serde_json::json!({
+            "appointment_date": local_date.format("%Y-%m-%d").to_string(),
+            "appointment_time": local_date.format("%H:%M").to_string(),
+        });
+
+        let request = CreateNotificationRequest {
+            patient_id: Some(patient_id),
+            appointment_id: None, // The slot is not an appointment yet
+            notification_type: "WAITLIST_OFFER".to_string(),
+            delivery_method: "EMAIL".to_string(),
+            recipient_email: Some(patient_email.to_string()),
+            recipient_name: Some(patient_name.to_string()),
+            subject: Some(subject),
+            message_body: body,
+            scheduled_for: None, // Send immediately
+            priority: Some(1),   // The offer expires
+            metadata: Some(metadata),
+        };
+
+        // Create notification record
+        let notification_response = self.create_notification(request, created_by).await?;
+
+        // Immediately send the notification (don't wait for scheduler)
+        match self.get_notification_by_id(notification_response.id, created_by).await {
+            Ok(Some(n)) => {
+                match self.process_notification(&n, created_by).await {
+                    Ok(result) if result.success => {
+                        info!("Waitlist offer sent immediately to patient {}", patient_id);
+                    }
+                    Ok(result) => {
+                        warn!("Waitlist offer failed for patient {}: {}", patient_id, result.message);
+                    }
+                    Err(e) => {
+                        warn!("Failed to process waitlist offer: {}", e);
+                    }
+                }
+            }
+            Ok(None) => {
+                warn!("Could not find notification {} for immediate send", notification_response.id);
+            }
+            Err(e) => {
+                warn!("Failed to fetch notification for immediate send: {}", e);
+            }
+        }
+
+        Ok(notification_response)
+    }
+
     /// Cancel all pending notifications for an appointment
     pub async fn cancel_appointment_notifications(&self, appointment_id: Uuid, user_id: Uuid) -> Result<i64> {
-        // Start transaction and set RLS context for UPDATE
-        let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-        Self::set_rls_context(&mut tx, user_id).await?;
+        let mut tx = begin_with_rls(&self.pool, user_id)
+            .await
+            .context("Failed to begin transaction")?;
 
         let result = sqlx::query!(
             r#"
@@ -1166,9 +1909,9 @@ This is an automated message from DocPat Medical Practice Management System.
     /// Get raw notification record by ID (for internal processing)
     /// Requires user_id for RLS context
     async fn get_notification_by_id(&self, id: Uuid, user_id: Uuid) -> Result<Option<Notification>> {
-        // Start transaction and set RLS context for SELECT
-        let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-        Self::set_rls_context(&mut tx, user_id).await?;
+        let mut tx = begin_with_rls(&self.pool, user_id)
+            .await
+            .context("Failed to begin transaction")?;
 
         let notification = sqlx::query_as!(
             Notification,
@@ -1215,6 +1958,168 @@ This is an automated message from DocPat Medical Practice Management System.
         Ok(result)
     }
 
+    // ========================================================================
+    // PRESCRIPTION NOTIFICATION HELPERS
+    // ========================================================================
+
+    /// Queue a reminder to a patient whose prescription is past its expected
+    /// refill date
+    #[allow(clippy::too_many_arguments)]
+    pub async fn queue_refill_reminder(
+        &self,
+        patient_id: Uuid,
+        prescription_id: Uuid,
+        patient_email: &str,
+        patient_name: &str,
+        medication_name: &str,
+        expected_refill_date: chrono::NaiveDate,
+        doctor_name: &str,
+        created_by: Uuid,
+    ) -> Result<NotificationResponse> {
+        let (subject, body) = generate_refill_reminder_email(
+            patient_name,
+            medication_name,
+            expected_refill_date,
+            doctor_name,
+        );
+
+        let metadata = serde_json::json!({
+            "prescription_id": prescription_id,
+            "expected_refill_date": expected_refill_date.format("%Y-%m-%d").to_string(),
+        });
+
+        let request = CreateNotificationRequest {
+            patient_id: Some(patient_id),
+            appointment_id: None,
+            notification_type: NotificationType::RefillReminder.as_str().to_string(),
+            delivery_method: "EMAIL".to_string(),
+            recipient_email: Some(patient_email.to_string()),
+            recipient_name: Some(patient_name.to_string()),
+            subject: Some(subject),
+            message_body: body,
+            scheduled_for: None,
+            priority: Some(5),
+            metadata: Some(metadata),
+        };
+
+        self.create_notification(request, created_by).await
+    }
+
+    /// Queue a recall reminder to a patient overdue for follow-up
+    ///
+    /// `subject` and `message` come from the recall rule; the metadata ties
+    /// the notification to the recall list entry.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn queue_recall_reminder(
+        &self,
+        patient_id: Uuid,
+        patient_email: &str,
+        patient_name: &str,
+        subject: &str,
+        message: &str,
+        rule_id: Uuid,
+        entry_id: Uuid,
+        created_by: Uuid,
+    ) -> Result<NotificationResponse> {
+        let body = generate_recall_reminder_email(patient_name, message);
+
+        let metadata = serde_json::json!({
+            "recall_rule_id": rule_id,
+            "recall_entry_id": entry_id,
+        });
+
+        let request = CreateNotificationRequest {
+            patient_id: Some(patient_id),
+            appointment_id: None,
+            notification_type: NotificationType::FollowUpReminder.as_str().to_string(),
+            delivery_method: "EMAIL".to_string(),
+            recipient_email: Some(patient_email.to_string()),
+            recipient_name: Some(patient_name.to_string()),
+            subject: Some(subject.to_string()),
+            message_body: body,
+            scheduled_for: None,
+            priority: Some(5),
+            metadata: Some(metadata),
+        };
+
+        self.create_notification(request, created_by).await
+    }
+
+    // ========================================================================
+    // PREVENTIVE REMINDER HELPERS
+    // ========================================================================
+
+    /// Queue a birthday greeting to a patient
+    ///
+    /// `message` is the practice's greeting (`birthday_greeting_message`).
+    pub async fn queue_birthday_greeting(
+        &self,
+        patient_id: Uuid,
+        patient_email: &str,
+        patient_name: &str,
+        message: &str,
+        birthday: chrono::NaiveDate,
+        created_by: Uuid,
+    ) -> Result<NotificationResponse> {
+        let (subject, body) = generate_birthday_greeting_email(patient_name, message);
+
+        let metadata = serde_json::json!({
+            "birthday": birthday.format("%Y-%m-%d").to_string(),
+        });
+
+        let request = CreateNotificationRequest {
+            patient_id: Some(patient_id),
+            appointment_id: None,
+            notification_type: NotificationType::BirthdayGreeting.as_str().to_string(),
+            delivery_method: "EMAIL".to_string(),
+            recipient_email: Some(patient_email.to_string()),
+            recipient_name: Some(patient_name.to_string()),
+            subject: Some(subject),
+            message_body: body,
+            scheduled_for: None,
+            priority: Some(3),
+            metadata: Some(metadata),
+        };
+
+        self.create_notification(request, created_by).await
+    }
+
+    /// Queue a reminder to a patient due for a preventive screening
+    ///
+    /// The metadata records the program code, which is how the scheduler
+    /// knows when the patient was last reminded of it.
+    pub async fn queue_screening_reminder(
+        &self,
+        patient_id: Uuid,
+        patient_email: &str,
+        patient_name: &str,
+        program_code: &str,
+        program_name: &str,
+        created_by: Uuid,
+    ) -> Result<NotificationResponse> {
+        let (subject, body) = generate_screening_reminder_email(patient_name, program_name);
+
+        let metadata = serde_json::json!({
+            "screening_program": program_code,
+        });
+
+        let request = CreateNotificationRequest {
+            patient_id: Some(patient_id),
+            appointment_id: None,
+            notification_type: NotificationType::ScreeningReminder.as_str().to_string(),
+            delivery_method: "EMAIL".to_string(),
+            recipient_email: Some(patient_email.to_string()),
+            recipient_name: Some(patient_name.to_string()),
+            subject: Some(subject),
+            message_body: body,
+            scheduled_for: None,
+            priority: Some(5),
+            metadata: Some(metadata),
+        };
+
+        self.create_notification(request, created_by).await
+    }
+
     /// Send a test email to verify SMTP configuration
     pub async fn send_test_email(&self, to_email: &str, to_name: &str) -> Result<EmailResult> {
         let subject = "DocPat - Test Email";
@@ -1242,140 +2147,426 @@ This is an automated message from DocPat Medical Practice Management System.
 // ============================================================================
 
 /// Generate appointment reminder email content
+///
+/// With `response_links`, the patient can confirm or cancel from the email.
 pub fn generate_appointment_reminder_email(
     patient_name: &str,
     appointment_date: &chrono::DateTime<Utc>,
-    doctor_name: &str,
+    doctor_name: &str,
+    appointment_type: &str,
+    response_links: Option<&AppointmentResponseLinks>,
+) -> (String, String) {
+    // Convert UTC to local timezone (Europe/Rome) for display
+    let local_date = appointment_date.with_timezone(&Rome);
+    let formatted_date = local_date.format("%A, %B %d, %Y at %H:%M").to_string();
+
+    let subject = format!("Appointment Reminder - {}", formatted_date);
+
+    let response_text = match response_links {
+        Some(links) => format!(
+            "Please confirm your appointment:\n{}\n\nIf you cannot attend, cancel it here so the time can be offered to another patient:\n{}\n\nTo reschedule, please contact us.",
+            links.confirm_url, links.cancel_url
+        ),
+        None => "If you need to reschedule or cancel, please contact us as soon as possible.".to_string(),
+    };
+
+    let body = format!(
+        r#"Dear {},
+
+This is a reminder for your upcoming appointment:
+
+📅 Date: {}
+👨‍⚕️ Doctor: {}
+📋 Type: {}
+
+Please arrive 10-15 minutes early to complete any necessary paperwork.
+
+{}
+
+Best regards,
+DocPat Medical Practice"#,
+        patient_name, formatted_date, doctor_name, appointment_type, response_text
+    );
+
+    (subject, body)
+}
+
+/// Generate appointment booked email content (sent when appointment is created/scheduled)
+pub fn generate_appointment_booked_email(
+    patient_name: &str,
+    appointment_date: &chrono::DateTime<Utc>,
+    doctor_name: &str,
+    appointment_type: &str,
+) -> (String, String) {
+    // Convert UTC to local timezone (Europe/Rome) for display
+    let local_date = appointment_date.with_timezone(&Rome);
+    let formatted_date = local_date.format("%A, %B %d, %Y at %H:%M").to_string();
+
+    let subject = "Appointment Scheduled".to_string();
+
+    let body = format!(
+        r#"Dear {},
+
+Your appointment has been scheduled:
+
+📅 Date: {}
+👨‍⚕️ Doctor: {}
+📋 Type: {}
+
+You will receive a reminder before your appointment.
+
+If you need to reschedule or cancel, please contact us as soon as possible.
+
+Best regards,
+DocPat Medical Practice"#,
+        patient_name, formatted_date, doctor_name, appointment_type
+    );
+
+    (subject, body)
+}
+
+/// Generate appointment confirmation email content (sent when appointment is confirmed)
+pub fn generate_appointment_confirmation_email(
+    patient_name: &str,
+    appointment_date: &chrono::DateTime<Utc>,
+    doctor_name: &str,
+    appointment_type: &str,
+) -> (String, String) {
+    // Convert UTC to local timezone (Europe/Rome) for display
+    let local_date = appointment_date.with_timezone(&Rome);
+    let formatted_date = local_date.format("%A, %B %d, %Y at %H:%M").to_string();
+
+    let subject = "Appointment Confirmed".to_string();
+
+    let body = format!(
+        r#"Dear {},
+
+Your appointment has been confirmed:
+
+📅 Date: {}
+👨‍⚕️ Doctor: {}
+📋 Type: {}
+
+We look forward to seeing you. You will receive a reminder before your appointment.
+
+If you need to reschedule or cancel, please contact us as soon as possible.
+
+Best regards,
+DocPat Medical Practice"#,
+        patient_name, formatted_date, doctor_name, appointment_type
+    );
+
+    (subject, body)
+}
+
+/// Generate appointment cancellation email content
+pub fn generate_appointment_cancellation_email(
+    patient_name: &str,
+    appointment_date: &chrono::DateTime<Utc>,
+    doctor_name: &str,
+    appointment_type: &str,
+    cancellation_reason: Option<&str>,
+) -> (String, String) {
+    // Convert UTC to local timezone (Europe/Rome) for display
+    let local_date = appointment_date.with_timezone(&Rome);
+    let formatted_date = local_date.format("%A, %B %d, %Y at %H:%M").to_string();
+
+    let subject = "Appointment Cancelled".to_string();
+
+    let reason_text = match cancellation_reason {
+        Some(reason) if !reason.is_empty() => format!("\n\nReason: {}", reason),
+        _ => String::new(),
+    };
+
+    let body = format!(
+        r#"Dear {},
+
+We regret to inform you that your appointment has been cancelled:
+
+📅 Original Date: {}
+👨‍⚕️ Doctor: {}
+📋 Type: {}{}
+
+We apologize for any inconvenience this may cause. Please contact us to reschedule at your earliest convenience.
+
+Best regards,
+DocPat Medical Practice"#,
+        patient_name, formatted_date, doctor_name, appointment_type, reason_text
+    );
+
+    (subject, body)
+}
+
+/// Generate the email telling a provider that a patient cancelled from a
+/// reminder link
+pub fn generate_patient_cancellation_email(
+    doctor_name: &str,
+    patient_name: &str,
+    appointment_date: &chrono::DateTime<Utc>,
     appointment_type: &str,
 ) -> (String, String) {
-    // Convert UTC to local timezone (Europe/Rome) for display
     let local_date = appointment_date.with_timezone(&Rome);
     let formatted_date = local_date.format("%A, %B %d, %Y at %H:%M").to_string();
 
-    let subject = format!("Appointment Reminder - {}", formatted_date);
+    let subject = format!("Appointment Cancelled by Patient - {}", formatted_date);
 
     let body = format!(
         r#"Dear {},
 
-This is a reminder for your upcoming appointment:
+{} has cancelled their appointment from the reminder email:
 
 📅 Date: {}
-👨‍⚕️ Doctor: {}
 📋 Type: {}
 
-Please arrive 10-15 minutes early to complete any necessary paperwork.
+The time is free again in your calendar.
 
-If you need to reschedule or cancel, please contact us as soon as possible.
+Best regards,
+DocPat Medical Practice"#,
+        doctor_name, patient_name, formatted_date, appointment_type
+    );
+
+    (subject, body)
+}
+
+/// Generate the survey email sent to a patient after a completed
+/// appointment
+pub fn generate_visit_survey_email(
+    patient_name: &str,
+    appointment_date: &chrono::DateTime<Utc>,
+    doctor_name: &str,
+    survey_url: &str,
+    valid_days: i64,
+) -> (String, String) {
+    let local_date = appointment_date.with_timezone(&Rome);
+    let formatted_date = local_date.format("%A, %B %d, %Y").to_string();
+
+    let subject = "How was your visit?".to_string();
+
+    let body = format!(
+        r#"Dear {},
+
+Thank you for your visit on {} with {}.
+
+We would like to know how it went. On a scale from 0 to 10, how likely are you to recommend our practice to a friend or family member? You can also leave us a comment. It takes less than a minute:
+
+{}
+
+The link is valid for {} days. Your answer helps us improve; it is not added to your medical record.
 
 Best regards,
 DocPat Medical Practice"#,
-        patient_name, formatted_date, doctor_name, appointment_type
+        patient_name, formatted_date, doctor_name, survey_url, valid_days
     );
 
     (subject, body)
 }
 
-/// Generate appointment booked email content (sent when appointment is created/scheduled)
-pub fn generate_appointment_booked_email(
+/// Generate the email with the join link of a televisit
+pub fn generate_televisit_invitation_email(
     patient_name: &str,
     appointment_date: &chrono::DateTime<Utc>,
     doctor_name: &str,
-    appointment_type: &str,
+    join_link: &str,
 ) -> (String, String) {
-    // Convert UTC to local timezone (Europe/Rome) for display
     let local_date = appointment_date.with_timezone(&Rome);
     let formatted_date = local_date.format("%A, %B %d, %Y at %H:%M").to_string();
 
-    let subject = "Appointment Scheduled".to_string();
+    let subject = format!("Your video visit - {}", local_date.format("%d/%m/%Y %H:%M"));
 
     let body = format!(
         r#"Dear {},
 
-Your appointment has been scheduled:
+Your video visit with {} is scheduled for {}.
 
-📅 Date: {}
-👨‍⚕️ Doctor: {}
-📋 Type: {}
+At the time of the visit, open this link from a computer or smartphone with a camera and microphone:
 
-You will receive a reminder before your appointment.
+{}
+
+The link opens {} minutes before the visit. Please join from a quiet, private place. This link is personal: do not forward it.
 
 If you need to reschedule or cancel, please contact us as soon as possible.
 
 Best regards,
 DocPat Medical Practice"#,
-        patient_name, formatted_date, doctor_name, appointment_type
+        patient_name, doctor_name, formatted_date, join_link, TELEVISIT_JOIN_EARLY_MINUTES
     );
 
     (subject, body)
 }
 
-/// Generate appointment confirmation email content (sent when appointment is confirmed)
-pub fn generate_appointment_confirmation_email(
+/// Generate the email offering a freed slot to a waitlisted patient
+pub fn generate_waitlist_offer_email(
     patient_name: &str,
-    appointment_date: &chrono::DateTime<Utc>,
+    slot_start: &chrono::DateTime<Utc>,
     doctor_name: &str,
-    appointment_type: &str,
+    links: &WaitlistOfferLinks,
+    expires_at: &chrono::DateTime<Utc>,
 ) -> (String, String) {
-    // Convert UTC to local timezone (Europe/Rome) for display
-    let local_date = appointment_date.with_timezone(&Rome);
+    let local_date = slot_start.with_timezone(&Rome);
     let formatted_date = local_date.format("%A, %B %d, %Y at %H:%M").to_string();
+    let formatted_expiry = expires_at.with_timezone(&Rome).format("%d/%m/%Y %H:%M").to_string();
 
-    let subject = "Appointment Confirmed".to_string();
+    let subject = format!(
+        "An earlier appointment is available - {}",
+        local_date.format("%d/%m/%Y %H:%M")
+    );
 
     let body = format!(
         r#"Dear {},
 
-Your appointment has been confirmed:
+An appointment has become available and you are on our waitlist:
 
 📅 Date: {}
 👨‍⚕️ Doctor: {}
-📋 Type: {}
 
-We look forward to seeing you. You will receive a reminder before your appointment.
+To take it, open this link:
+{}
 
-If you need to reschedule or cancel, please contact us as soon as possible.
+If you cannot come, please let us know so that we can offer it to someone else:
+{}
+
+The offer is valid until {}. It has also been sent to other patients on the waitlist: the first to accept gets the appointment. You stay on the waitlist if you decline.
 
 Best regards,
 DocPat Medical Practice"#,
-        patient_name, formatted_date, doctor_name, appointment_type
+        patient_name,
+        formatted_date,
+        doctor_name,
+        links.accept_url,
+        links.decline_url,
+        formatted_expiry
     );
 
     (subject, body)
 }
 
-/// Generate appointment cancellation email content
-pub fn generate_appointment_cancellation_email(
+/// Generate the reminder sent to a referring doctor about an overdue referral
+///
+/// Names the patient and the specialty only; the clinical reason stays in
+/// the record.
+pub fn generate_referral_reminder_email(
+    doctor_name: &str,
     patient_name: &str,
-    appointment_date: &chrono::DateTime<Utc>,
+    specialty: &str,
+    response_due_date: chrono::NaiveDate,
+    days_overdue: i64,
+) -> (String, String) {
+    let subject = format!("Referral Response Overdue - {}", specialty);
+
+    let body = format!(
+        r#"Dear {},
+
+The specialist response to your referral is overdue:
+
+👤 Patient: {}
+🩺 Specialty: {}
+📅 Response expected by: {} ({} days ago)
+
+Please follow up with the specialist or the patient, and record the response in the patient record once received.
+
+Best regards,
+DocPat Medical Practice"#,
+        doctor_name,
+        patient_name,
+        specialty,
+        response_due_date.format("%d/%m/%Y"),
+        days_overdue
+    );
+
+    (subject, body)
+}
+
+/// Generate the reminder sent to a patient whose medication should have
+/// been refilled
+pub fn generate_refill_reminder_email(
+    patient_name: &str,
+    medication_name: &str,
+    expected_refill_date: chrono::NaiveDate,
     doctor_name: &str,
-    appointment_type: &str,
-    cancellation_reason: Option<&str>,
 ) -> (String, String) {
-    // Convert UTC to local timezone (Europe/Rome) for display
-    let local_date = appointment_date.with_timezone(&Rome);
-    let formatted_date = local_date.format("%A, %B %d, %Y at %H:%M").to_string();
+    let subject = format!("Prescription Refill Reminder - {}", medication_name);
 
-    let subject = "Appointment Cancelled".to_string();
+    let body = format!(
+        r#"Dear {},
 
-    let reason_text = match cancellation_reason {
-        Some(reason) if !reason.is_empty() => format!("\n\nReason: {}", reason),
-        _ => String::new(),
-    };
+According to your prescription, your supply of the following medication should have run out:
+
+💊 Medication: {}
+📅 Refill expected by: {}
+👨‍⚕️ Prescribed by: {}
+
+If you are still taking it, please collect your refill or contact the practice for a new prescription. If you have stopped taking it, please let us know.
+
+Best regards,
+DocPat Medical Practice"#,
+        patient_name,
+        medication_name,
+        expected_refill_date.format("%d/%m/%Y"),
+        doctor_name
+    );
+
+    (subject, body)
+}
+
+/// Generate the body of a recall reminder: the rule's message with a
+/// greeting and how to stop the reminders
+pub fn generate_recall_reminder_email(patient_name: &str, message: &str) -> String {
+    format!(
+        r#"Dear {},
+
+{}
+
+If you would rather not receive these reminders, please contact the practice.
+
+Best regards,
+DocPat Medical Practice"#,
+        patient_name,
+        message.trim()
+    )
+}
+
+/// Generate the birthday greeting sent to a patient
+pub fn generate_birthday_greeting_email(patient_name: &str, message: &str) -> (String, String) {
+    let subject = "Happy Birthday!".to_string();
 
     let body = format!(
         r#"Dear {},
 
-We regret to inform you that your appointment has been cancelled:
+{}
 
-📅 Original Date: {}
-👨‍⚕️ Doctor: {}
-📋 Type: {}{}
+Best regards,
+DocPat Medical Practice"#,
+        patient_name,
+        message.trim()
+    );
 
-We apologize for any inconvenience this may cause. Please contact us to reschedule at your earliest convenience.
+    (subject, body)
+}
+
+/// Generate the reminder sent to a patient due for a preventive screening
+///
+/// Names the screening only; whether the patient is due follows from the
+/// practice's screening programs, not from their record.
+pub fn generate_screening_reminder_email(
+    patient_name: &str,
+    screening_name: &str,
+) -> (String, String) {
+    let subject = format!("Preventive Screening Reminder - {}", screening_name);
+
+    let body = format!(
+        r#"Dear {},
+
+Based on your age, you are due for the following preventive screening:
+
+🩺 Screening: {}
+
+Regular screening helps detect health problems early, when they are easier to treat. Please contact the practice to book it, or let us know if you have already had it elsewhere.
+
+If you would rather not receive these reminders, please contact the practice.
 
 Best regards,
 DocPat Medical Practice"#,
-        patient_name, formatted_date, doctor_name, appointment_type, reason_text
+        patient_name, screening_name
     );
 
     (subject, body)
@@ -1386,6 +2577,18 @@ mod tests {
     use super::*;
     use chrono::TimeZone;
 
+    #[test]
+    fn test_worker_id_is_stable() {
+        let id = worker_id();
+        assert!(!id.is_empty());
+        assert_eq!(worker_id(), id);
+    }
+
+    #[test]
+    fn test_heartbeat_renews_well_before_lease_expiry() {
+        assert!(HEARTBEAT_INTERVAL_SECS * 2 < LEASE_DURATION_SECS);
+    }
+
     #[test]
     fn test_generate_reminder_email() {
         let date = Utc::now() + Duration::days(1);
@@ -1394,6 +2597,7 @@ mod tests {
             &date,
             "Dr. Smith",
             "General Checkup",
+            None,
         );
 
         assert!(subject.contains("Appointment Reminder"));
@@ -1402,6 +2606,266 @@ mod tests {
         assert!(body.contains("General Checkup"));
     }
 
+    #[test]
+    fn test_generate_reminder_email_with_links() {
+        let date = Utc::now() + Duration::days(1);
+        let links = AppointmentResponseLinks {
+            confirm_url: "https://studio.example.com/appointments/respond?token=c".to_string(),
+            cancel_url: "https://studio.example.com/appointments/respond?token=x".to_string(),
+        };
+        let (_, body) = generate_appointment_reminder_email(
+            "John Doe",
+            &date,
+            "Dr. Smith",
+            "General Checkup",
+            Some(&links),
+        );
+
+        assert!(body.contains(&links.confirm_url));
+        assert!(body.contains(&links.cancel_url));
+        assert!(!body.contains("as soon as possible"));
+    }
+
+    #[test]
+    fn test_appointment_link_token() {
+        let key =
+            EncryptionKey::from_base64("MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWY=").unwrap();
+        let now = Utc::now();
+        let appointment_id = Uuid::new_v4();
+        let patient_id = Uuid::new_v4();
+        let expires_at = now + Duration::days(1);
+
+        let token = NotificationService::generate_appointment_link_token(
+            &key,
+            appointment_id,
+            patient_id,
+            AppointmentLinkAction::Cancel,
+            expires_at,
+        );
+        let claims = NotificationService::verify_appointment_link_token(&key, &token, now).unwrap();
+        assert_eq!(claims.appointment_id, appointment_id);
+        assert_eq!(claims.patient_id, patient_id);
+        assert_eq!(claims.action, AppointmentLinkAction::Cancel);
+        assert_eq!(claims.expires_at.timestamp(), expires_at.timestamp());
+
+        // Expired
+        assert!(NotificationService::verify_appointment_link_token(
+            &key,
+            &token,
+            expires_at + Duration::seconds(1)
+        )
+        .is_none());
+
+        // Action swapped without re-signing
+        let tampered = token.replace(".cancel.", ".confirm.");
+        assert!(NotificationService::verify_appointment_link_token(&key, &tampered, now).is_none());
+
+        let links = NotificationService::appointment_response_links(
+            &key,
+            "https://studio.example.com/",
+            appointment_id,
+            patient_id,
+            expires_at,
+        );
+        assert!(links
+            .confirm_url
+            .starts_with("https://studio.example.com/appointments/respond?token="));
+        assert_ne!(links.confirm_url, links.cancel_url);
+    }
+
+    #[test]
+    fn test_visit_survey_token() {
+        let key =
+            EncryptionKey::from_base64("MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWY=").unwrap();
+        let now = Utc::now();
+        let survey_id = Uuid::new_v4();
+        let patient_id = Uuid::new_v4();
+        let expires_at = now + Duration::days(14);
+
+        let token = NotificationService::generate_visit_survey_token(
+            &key,
+            survey_id,
+            patient_id,
+            expires_at,
+        );
+        let claims = NotificationService::verify_visit_survey_token(&key, &token, now).unwrap();
+        assert_eq!(claims.survey_id, survey_id);
+        assert_eq!(claims.patient_id, patient_id);
+        assert_eq!(claims.expires_at.timestamp(), expires_at.timestamp());
+
+        // Expired
+        assert!(NotificationService::verify_visit_survey_token(
+            &key,
+            &token,
+            expires_at + Duration::seconds(1)
+        )
+        .is_none());
+
+        // An appointment link token is not a survey token
+        let link_token = NotificationService::generate_appointment_link_token(
+            &key,
+            survey_id,
+            patient_id,
+            AppointmentLinkAction::Confirm,
+            expires_at,
+        );
+        assert!(NotificationService::verify_visit_survey_token(&key, &link_token, now).is_none());
+
+        let url = NotificationService::visit_survey_url(
+            &key,
+            "https://studio.example.com/",
+            survey_id,
+            patient_id,
+            expires_at,
+        );
+        assert!(url.starts_with("https://studio.example.com/surveys/respond?token="));
+    }
+
+    #[test]
+    fn test_generate_visit_survey_email() {
+        let date = Utc::now() - Duration::days(1);
+        let (subject, body) = generate_visit_survey_email(
+            "Jane Doe",
+            &date,
+            "Dr. Johnson",
+            "https://studio.example.com/surveys/respond?token=t",
+            14,
+        );
+
+        assert_eq!(subject, "How was your visit?");
+        assert!(body.contains("Jane Doe"));
+        assert!(body.contains("Dr. Johnson"));
+        assert!(body.contains("https://studio.example.com/surveys/respond?token=t"));
+        assert!(body.contains("14 days"));
+    }
+
+    #[test]
+    fn test_televisit_token() {
+        let key =
+            EncryptionKey::from_base64("MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWY=").unwrap();
+        let now = Utc::now();
+        let appointment_id = Uuid::new_v4();
+        let patient_id = Uuid::new_v4();
+        let expires_at = now + Duration::days(3);
+
+        let token =
+            NotificationService::generate_televisit_token(&key, appointment_id, patient_id, expires_at);
+        let claims = NotificationService::verify_televisit_token(&key, &token, now).unwrap();
+        assert_eq!(claims.appointment_id, appointment_id);
+        assert_eq!(claims.patient_id, patient_id);
+        assert_eq!(claims.expires_at.timestamp(), expires_at.timestamp());
+
+        // Expired
+        assert!(NotificationService::verify_televisit_token(
+            &key,
+            &token,
+            expires_at + Duration::seconds(1)
+        )
+        .is_none());
+
+        // A survey token for the same ids is not a televisit token
+        let survey_token = NotificationService::generate_visit_survey_token(
+            &key,
+            appointment_id,
+            patient_id,
+            expires_at,
+        );
+        assert!(NotificationService::verify_televisit_token(&key, &survey_token, now).is_none());
+
+        let url = NotificationService::televisit_url(
+            &key,
+            "https://studio.example.com/",
+            appointment_id,
+            patient_id,
+            expires_at,
+        );
+        assert!(url.starts_with("https://studio.example.com/televisit/join?token="));
+    }
+
+    #[test]
+    fn test_waitlist_offer_token() {
+        let key =
+            EncryptionKey::from_base64("MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWY=").unwrap();
+        let now = Utc::now();
+        let offer_id = Uuid::new_v4();
+        let patient_id = Uuid::new_v4();
+        let expires_at = now + Duration::hours(2);
+
+        let token = NotificationService::generate_waitlist_offer_token(
+            &key,
+            offer_id,
+            patient_id,
+            WaitlistOfferAction::Accept,
+            expires_at,
+        );
+        let claims = NotificationService::verify_waitlist_offer_token(&key, &token, now).unwrap();
+        assert_eq!(claims.offer_id, offer_id);
+        assert_eq!(claims.patient_id, patient_id);
+        assert_eq!(claims.action, WaitlistOfferAction::Accept);
+
+        // Expired
+        assert!(NotificationService::verify_waitlist_offer_token(
+            &key,
+            &token,
+            expires_at + Duration::seconds(1)
+        )
+        .is_none());
+
+        // The action is signed
+        let declined = token.replacen(".accept.", ".decline.", 1);
+        assert!(NotificationService::verify_waitlist_offer_token(&key, &declined, now).is_none());
+
+        let links = NotificationService::waitlist_offer_links(
+            &key,
+            "https://studio.example.com/",
+            offer_id,
+            patient_id,
+            expires_at,
+        );
+        assert!(links.accept_url.starts_with("https://studio.example.com/waitlist/respond?token="));
+        assert_ne!(links.accept_url, links.decline_url);
+    }
+
+    #[test]
+    fn test_generate_waitlist_offer_email() {
+        let date = Utc::now() + Duration::days(2);
+        let links = WaitlistOfferLinks {
+            accept_url: "https://studio.example.com/waitlist/respond?token=a".to_string(),
+            decline_url: "https://studio.example.com/waitlist/respond?token=d".to_string(),
+        };
+        let (subject, body) = generate_waitlist_offer_email(
+            "Jane Doe",
+            &date,
+            "Dr. Johnson",
+            &links,
+            &(Utc::now() + Duration::hours(2)),
+        );
+
+        assert!(subject.starts_with("An earlier appointment is available"));
+        assert!(body.contains("Jane Doe"));
+        assert!(body.contains("Dr. Johnson"));
+        assert!(body.contains(&links.accept_url));
+        assert!(body.contains(&links.decline_url));
+        assert!(body.contains("first to accept"));
+    }
+
+    #[test]
+    fn test_generate_televisit_invitation_email() {
+        let date = Utc::now() + Duration::days(2);
+        let (subject, body) = generate_televisit_invitation_email(
+            "Jane Doe",
+            &date,
+            "Dr. Johnson",
+            "https://studio.example.com/televisit/join?token=t",
+        );
+
+        assert!(subject.starts_with("Your video visit"));
+        assert!(body.contains("Jane Doe"));
+        assert!(body.contains("Dr. Johnson"));
+        assert!(body.contains("https://studio.example.com/televisit/join?token=t"));
+        assert!(body.contains("30 minutes before"));
+    }
+
     #[test]
     fn test_generate_confirmation_email() {
         let date = Utc::now() + Duration::days(7);
@@ -1480,6 +2944,7 @@ mod tests {
             &date,
             "Dr. Format",
             "Testing",
+            None,
         );
 
         // Subject should contain the formatted date
@@ -1519,6 +2984,7 @@ mod tests {
             &date,
             "Dr. Doctor",
             long_type,
+            None,
         );
 
         assert!(body.contains(long_type));
@@ -1533,6 +2999,7 @@ mod tests {
             &date,
             "Doctor",
             "Type",
+            None,
         );
         let (_, confirmation_body) = generate_appointment_confirmation_email(
             "Patient",
@@ -1562,6 +3029,7 @@ mod tests {
             &date,
             "Doctor",
             "Type",
+            None,
         );
 
         // Check for emoji icons in templates
@@ -1578,6 +3046,7 @@ mod tests {
             &date,
             "Doctor",
             "Type",
+            None,
         );
 
         assert!(body.contains("arrive 10-15 minutes early"));
@@ -1616,7 +3085,7 @@ mod tests {
         let date = Utc::now() + Duration::days(1);
 
         let (reminder_subject, _) = generate_appointment_reminder_email(
-            "Patient", &date, "Doctor", "Type"
+            "Patient", &date, "Doctor", "Type", None
         );
         let (confirmation_subject, _) = generate_appointment_confirmation_email(
             "Patient", &date, "Doctor", "Type"
@@ -1645,4 +3114,53 @@ mod tests {
         // Cancellation emails should mention rescheduling
         assert!(cancellation_body.contains("reschedule") || cancellation_body.contains("new appointment"));
     }
+
+    #[test]
+    fn test_referral_reminder_email() {
+        let due = chrono::NaiveDate::from_ymd_opt(2026, 4, 10).unwrap();
+        let (subject, body) =
+            generate_referral_reminder_email("Dr. Rossi", "Mario Bianchi", "Cardiologia", due, 5);
+
+        assert_eq!(subject, "Referral Response Overdue - Cardiologia");
+        assert!(body.contains("Dear Dr. Rossi"));
+        assert!(body.contains("Mario Bianchi"));
+        assert!(body.contains("10/04/2026 (5 days ago)"));
+        assert!(body.contains("DocPat Medical Practice"));
+    }
+
+    #[test]
+    fn test_refill_reminder_email() {
+        let expected = chrono::NaiveDate::from_ymd_opt(2026, 4, 12).unwrap();
+        let (subject, body) = generate_refill_reminder_email(
+            "Mario Bianchi",
+            "Metformina 500 mg",
+            expected,
+            "Dr. Rossi",
+        );
+
+        assert_eq!(subject, "Prescription Refill Reminder - Metformina 500 mg");
+        assert!(body.contains("Dear Mario Bianchi"));
+        assert!(body.contains("Refill expected by: 12/04/2026"));
+        assert!(body.contains("Dr. Rossi"));
+    }
+
+    #[test]
+    fn test_birthday_greeting_email() {
+        let (subject, body) =
+            generate_birthday_greeting_email("Mario Bianchi", "  Best wishes from all of us!\n");
+
+        assert_eq!(subject, "Happy Birthday!");
+        assert!(body.contains("Dear Mario Bianchi"));
+        assert!(body.contains("\n\nBest wishes from all of us!\n\n"));
+        assert!(body.contains("DocPat Medical Practice"));
+    }
+
+    #[test]
+    fn test_screening_reminder_email() {
+        let (subject, body) = generate_screening_reminder_email("Anna Verdi", "Mammography");
+
+        assert_eq!(subject, "Preventive Screening Reminder - Mammography");
+        assert!(body.contains("Dear Anna Verdi"));
+        assert!(body.contains("Screening: Mammography"));
+    }
 }
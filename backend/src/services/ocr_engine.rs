@@ -0,0 +1,164 @@
+/*!
+ * OCR Engines
+ *
+ * Text recognition behind the `OcrEngine` trait, selected with OCR_PROVIDER:
+ * - `tesseract`: runs the local Tesseract binary on images (PDFs are skipped)
+ * - `http`: POSTs the file to an external service that answers
+ *   `{"text": "..."}`, for PDFs or better recognition than Tesseract's
+ *
+ * Engines only turn bytes into text; scheduling, storage and field
+ * suggestions are in `attachment_ocr_service`.
+ */
+
+use crate::config::{OcrConfig, OcrProvider};
+use anyhow::{Context, Result};
+use futures::future::BoxFuture;
+use serde::Deserialize;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::time::timeout;
+
+/// Recognizes the text of a document
+pub trait OcrEngine: Send + Sync {
+    /// Engine name, for logs
+    fn name(&self) -> &'static str;
+
+    /// Whether the engine can read files of this type
+    fn supports(&self, mime_type: &str) -> bool;
+
+    /// Recognize the text of a file
+    fn recognize<'a>(&'a self, content: &'a [u8], mime_type: &'a str)
+        -> BoxFuture<'a, Result<String>>;
+}
+
+/// Build the engine configured with OCR_PROVIDER
+pub fn build_ocr_engine(config: &OcrConfig) -> Result<Arc<dyn OcrEngine>> {
+    Ok(match &config.provider {
+        OcrProvider::Tesseract => Arc::new(TesseractOcrEngine {
+            binary: config.tesseract_path.clone(),
+            languages: config.languages.clone(),
+            timeout: config.timeout,
+        }),
+        OcrProvider::Http(endpoint) => Arc::new(HttpOcrEngine {
+            client: reqwest::Client::builder()
+                .timeout(config.timeout)
+                .build()
+                .context("Failed to build OCR HTTP client")?,
+            endpoint: endpoint.clone(),
+            auth_token: config.auth_token().map(str::to_string),
+        }),
+    })
+}
+
+/// Local Tesseract binary, fed through stdin so no plaintext copy of the
+/// file is written to disk
+pub struct TesseractOcrEngine {
+    binary: String,
+    languages: String,
+    timeout: Duration,
+}
+
+impl OcrEngine for TesseractOcrEngine {
+    fn name(&self) -> &'static str {
+        "tesseract"
+    }
+
+    fn supports(&self, mime_type: &str) -> bool {
+        matches!(mime_type, "image/jpeg" | "image/png")
+    }
+
+    fn recognize<'a>(
+        &'a self,
+        content: &'a [u8],
+        _mime_type: &'a str,
+    ) -> BoxFuture<'a, Result<String>> {
+        Box::pin(async move {
+            let mut child = Command::new(&self.binary)
+                .args(["stdin", "stdout", "-l", &self.languages])
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .kill_on_drop(true)
+                .spawn()
+                .with_context(|| format!("Failed to start {}", self.binary))?;
+
+            let mut stdin = child.stdin.take().context("Tesseract stdin unavailable")?;
+            let run = async {
+                stdin.write_all(content).await?;
+                drop(stdin);
+                child.wait_with_output().await
+            };
+
+            let output = timeout(self.timeout, run)
+                .await
+                .context("Tesseract timed out")?
+                .context("Tesseract failed")?;
+
+            if !output.status.success() {
+                anyhow::bail!(
+                    "Tesseract exited with {}: {}",
+                    output.status,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                );
+            }
+
+            Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+        })
+    }
+}
+
+/// External OCR service
+pub struct HttpOcrEngine {
+    client: reqwest::Client,
+    endpoint: String,
+    auth_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct HttpOcrResponse {
+    text: String,
+}
+
+impl OcrEngine for HttpOcrEngine {
+    fn name(&self) -> &'static str {
+        "http"
+    }
+
+    fn supports(&self, _mime_type: &str) -> bool {
+        true
+    }
+
+    fn recognize<'a>(
+        &'a self,
+        content: &'a [u8],
+        mime_type: &'a str,
+    ) -> BoxFuture<'a, Result<String>> {
+        Box::pin(async move {
+            let mut request = self
+                .client
+                .post(&self.endpoint)
+                .header(reqwest::header::CONTENT_TYPE, mime_type)
+                .body(content.to_vec());
+            if let Some(token) = &self.auth_token {
+                request = request.bearer_auth(token);
+            }
+
+            let response = request
+                .send()
+                .await
+                .context("OCR service unreachable")?
+                .error_for_status()
+                .context("OCR service rejected the file")?;
+
+            let body: HttpOcrResponse = response
+                .json()
+                .await
+                .context("Invalid OCR service response")?;
+
+            Ok(body.text)
+        })
+    }
+}
@@ -0,0 +1,161 @@
+/*!
+ * Overbooking Policy Service
+ *
+ * Per-provider overbooking policies. `AppointmentService` reads the policy
+ * of the provider when a booking asks to be overbooked; the policies hold
+ * no patient data.
+ */
+
+use crate::{
+    models::{OverbookingPolicy, SetOverbookingPolicyRequest},
+    utils::{AppError, Result},
+};
+use sqlx::{PgConnection, PgPool};
+use tracing::info;
+use uuid::Uuid;
+
+const POLICY_COLUMNS: &str = r#"
+    o.provider_id, u.first_name || ' ' || u.last_name AS provider_name,
+    o.max_parallel, o.appointment_types, o.start_time, o.end_time,
+    o.created_by, o.updated_by, o.created_at, o.updated_at
+"#;
+
+/// Overbooking policy service
+pub struct OverbookingService {
+    pool: PgPool,
+}
+
+impl OverbookingService {
+    /// Create a new overbooking policy service
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// All overbooking policies, by provider name
+    pub async fn list_policies(&self) -> Result<Vec<OverbookingPolicy>> {
+        let policies = sqlx::query_as::<_, OverbookingPolicy>(&format!(
+            r#"
+            SELECT {}
+            FROM provider_overbooking_policies o
+            JOIN users u ON u.id = o.provider_id
+            ORDER BY u.last_name, u.first_name
+            "#,
+            POLICY_COLUMNS
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(policies)
+    }
+
+    /// Set the overbooking policy of a provider, replacing any previous one
+    ///
+    /// Appointments already overbooked are kept.
+    pub async fn set_policy(
+        &self,
+        provider_id: Uuid,
+        request: &SetOverbookingPolicyRequest,
+        set_by: Uuid,
+    ) -> Result<OverbookingPolicy> {
+        let mut tx = self.pool.begin().await?;
+
+        let is_active: Option<bool> =
+            sqlx::query_scalar("SELECT is_active FROM users WHERE id = $1")
+                .bind(provider_id)
+                .fetch_optional(&mut *tx)
+                .await?;
+        match is_active {
+            None => {
+                return Err(AppError::NotFound(format!(
+                    "Provider {} not found",
+                    provider_id
+                )))
+            }
+            Some(false) => {
+                return Err(AppError::Validation(
+                    "The provider's account is not active".to_string(),
+                ))
+            }
+            Some(true) => {}
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO provider_overbooking_policies (
+                provider_id, max_parallel, appointment_types, start_time, end_time,
+                created_by, updated_by
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $6)
+            ON CONFLICT (provider_id) DO UPDATE SET
+                max_parallel = EXCLUDED.max_parallel,
+                appointment_types = EXCLUDED.appointment_types,
+                start_time = EXCLUDED.start_time,
+                end_time = EXCLUDED.end_time,
+                updated_by = EXCLUDED.updated_by
+            "#,
+        )
+        .bind(provider_id)
+        .bind(request.max_parallel)
+        .bind(&request.appointment_types)
+        .bind(request.start_time)
+        .bind(request.end_time)
+        .bind(set_by)
+        .execute(&mut *tx)
+        .await?;
+
+        let policy = find_policy(&mut tx, provider_id)
+            .await?
+            .ok_or_else(|| AppError::Internal("Overbooking policy not saved".to_string()))?;
+
+        tx.commit().await?;
+
+        info!(
+            "Overbooking policy of provider {} set to {} parallel bookings (set by {})",
+            provider_id, request.max_parallel, set_by
+        );
+
+        Ok(policy)
+    }
+
+    /// Remove the overbooking policy of a provider
+    ///
+    /// Appointments already overbooked are kept.
+    pub async fn remove_policy(&self, provider_id: Uuid) -> Result<()> {
+        let result =
+            sqlx::query("DELETE FROM provider_overbooking_policies WHERE provider_id = $1")
+                .bind(provider_id)
+                .execute(&self.pool)
+                .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound(format!(
+                "Provider {} has no overbooking policy",
+                provider_id
+            )));
+        }
+
+        info!("Overbooking policy removed for provider {}", provider_id);
+        Ok(())
+    }
+}
+
+/// Overbooking policy of a provider, read in the caller's transaction
+pub(crate) async fn find_policy(
+    conn: &mut PgConnection,
+    provider_id: Uuid,
+) -> Result<Option<OverbookingPolicy>> {
+    let policy = sqlx::query_as::<_, OverbookingPolicy>(&format!(
+        r#"
+        SELECT {}
+        FROM provider_overbooking_policies o
+        JOIN users u ON u.id = o.provider_id
+        WHERE o.provider_id = $1
+        "#,
+        POLICY_COLUMNS
+    ))
+    .bind(provider_id)
+    .fetch_optional(conn)
+    .await?;
+
+    Ok(policy)
+}
@@ -0,0 +1,246 @@
+/*!
+ * Password Policy Service
+ *
+ * Enforces password maximum age and reuse history:
+ * - `security.password_expiry_days`: passwords older than this must be
+ *   changed at next login (0 = never expire)
+ * - `security.password_history_count`: a new password may not match the
+ *   current one or any of the last N passwords
+ *
+ * Every password set through this service is recorded (hashed) in
+ * `password_history` and `users.password_changed_at` is updated.
+ */
+
+use chrono::{DateTime, Duration, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::services::SettingsService;
+use crate::utils::password::validate_password;
+use crate::utils::{AppError, PasswordHasherUtil, Result};
+
+/// Default password maximum age in days when the setting is missing
+const DEFAULT_MAX_AGE_DAYS: i64 = 90;
+
+/// Default number of previous passwords that cannot be reused
+const DEFAULT_HISTORY_COUNT: i64 = 5;
+
+/// Effective password policy
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PasswordPolicy {
+    /// Maximum password age in days (0 = never expire)
+    pub max_age_days: i64,
+    /// Number of previous passwords that cannot be reused
+    pub history_count: i64,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            max_age_days: DEFAULT_MAX_AGE_DAYS,
+            history_count: DEFAULT_HISTORY_COUNT,
+        }
+    }
+}
+
+impl PasswordPolicy {
+    /// Load the policy from system settings, falling back to defaults
+    pub async fn load(settings: &SettingsService) -> Self {
+        let max_age_days = settings
+            .get_setting_value::<i64>("security.password_expiry_days")
+            .await
+            .unwrap_or(None)
+            .unwrap_or(DEFAULT_MAX_AGE_DAYS)
+            .max(0);
+        let history_count = settings
+            .get_setting_value::<i64>("security.password_history_count")
+            .await
+            .unwrap_or(None)
+            .unwrap_or(DEFAULT_HISTORY_COUNT)
+            .max(0);
+
+        Self {
+            max_age_days,
+            history_count,
+        }
+    }
+
+    /// Whether a password last changed at `changed_at` has expired
+    ///
+    /// A missing timestamp counts as expired when expiry is enabled.
+    pub fn is_expired(&self, changed_at: Option<DateTime<Utc>>, now: DateTime<Utc>) -> bool {
+        if self.max_age_days == 0 {
+            return false;
+        }
+
+        match changed_at {
+            Some(changed_at) => now - changed_at >= Duration::days(self.max_age_days),
+            None => true,
+        }
+    }
+}
+
+/// Password policy service
+#[derive(Clone)]
+pub struct PasswordPolicyService {
+    pool: PgPool,
+}
+
+impl PasswordPolicyService {
+    /// Create a new password policy service
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// When the user's password was last changed
+    pub async fn password_changed_at(&self, user_id: Uuid) -> Result<Option<DateTime<Utc>>> {
+        let changed_at: Option<Option<DateTime<Utc>>> =
+            sqlx::query_scalar("SELECT password_changed_at FROM users WHERE id = $1")
+                .bind(user_id)
+                .fetch_optional(&self.pool)
+                .await?;
+
+        Ok(changed_at.flatten())
+    }
+
+    /// Whether the user's password has expired under the given policy
+    pub async fn is_password_expired(&self, user_id: Uuid, policy: &PasswordPolicy) -> Result<bool> {
+        let changed_at = self.password_changed_at(user_id).await?;
+        Ok(policy.is_expired(changed_at, Utc::now()))
+    }
+
+    /// Whether `password` matches the current password or one of the last
+    /// `history_count` passwords
+    pub async fn is_reused(&self, user_id: Uuid, password: &str, history_count: i64) -> Result<bool> {
+        let current_hash: Option<String> =
+            sqlx::query_scalar("SELECT password_hash FROM users WHERE id = $1")
+                .bind(user_id)
+                .fetch_optional(&self.pool)
+                .await?;
+
+        let mut hashes: Vec<String> = current_hash.into_iter().collect();
+
+        if history_count > 0 {
+            let history: Vec<String> = sqlx::query_scalar(
+                r#"
+                SELECT password_hash FROM password_history
+                WHERE user_id = $1
+                ORDER BY created_at DESC
+                LIMIT $2
+                "#,
+            )
+            .bind(user_id)
+            .bind(history_count)
+            .fetch_all(&self.pool)
+            .await?;
+            hashes.extend(history);
+        }
+
+        Ok(hashes
+            .iter()
+            .any(|hash| PasswordHasherUtil::verify_password(password, hash)))
+    }
+
+    /// Validate and set a new password
+    ///
+    /// Checks complexity and reuse history, then updates the hash, resets
+    /// `password_changed_at` and failed login counters, records the hash in
+    /// `password_history` and prunes entries beyond the history size.
+    ///
+    /// # Errors
+    ///
+    /// `Validation` if the password is too weak or was used recently
+    pub async fn set_password(
+        &self,
+        user_id: Uuid,
+        new_password: &str,
+        policy: &PasswordPolicy,
+    ) -> Result<()> {
+        validate_password(new_password).map_err(AppError::Validation)?;
+
+        if self.is_reused(user_id, new_password, policy.history_count).await? {
+            return Err(AppError::Validation(format!(
+                "Password must not match any of your last {} passwords",
+                policy.history_count.max(1)
+            )));
+        }
+
+        let password_hash = PasswordHasherUtil::hash_password(new_password)?;
+
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            UPDATE users
+            SET password_hash = $1, password_changed_at = NOW(),
+                failed_login_attempts = 0, locked_until = NULL, updated_at = NOW()
+            WHERE id = $2
+            "#,
+        )
+        .bind(&password_hash)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("INSERT INTO password_history (user_id, password_hash) VALUES ($1, $2)")
+            .bind(user_id)
+            .bind(&password_hash)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query(
+            r#"
+            DELETE FROM password_history
+            WHERE user_id = $1
+              AND id NOT IN (
+                  SELECT id FROM password_history
+                  WHERE user_id = $1
+                  ORDER BY created_at DESC
+                  LIMIT $2
+              )
+            "#,
+        )
+        .bind(user_id)
+        .bind(policy.history_count.max(1))
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_password_not_expired_within_max_age() {
+        let policy = PasswordPolicy { max_age_days: 90, history_count: 5 };
+        let now = Utc::now();
+        assert!(!policy.is_expired(Some(now - Duration::days(89)), now));
+    }
+
+    #[test]
+    fn test_password_expired_after_max_age() {
+        let policy = PasswordPolicy { max_age_days: 90, history_count: 5 };
+        let now = Utc::now();
+        assert!(policy.is_expired(Some(now - Duration::days(90)), now));
+        assert!(policy.is_expired(Some(now - Duration::days(365)), now));
+    }
+
+    #[test]
+    fn test_missing_changed_at_counts_as_expired() {
+        let policy = PasswordPolicy::default();
+        assert!(policy.is_expired(None, Utc::now()));
+    }
+
+    #[test]
+    fn test_zero_max_age_never_expires() {
+        let policy = PasswordPolicy { max_age_days: 0, history_count: 5 };
+        let now = Utc::now();
+        assert!(!policy.is_expired(None, now));
+        assert!(!policy.is_expired(Some(now - Duration::days(3650)), now));
+    }
+}
@@ -0,0 +1,348 @@
+/*!
+ * Patient Allergy Service
+ *
+ * Structured allergies and intolerances, under RLS (doctors and nurses read
+ * and record, doctors edit and delete). Substance, reaction and notes are
+ * encrypted before storage.
+ *
+ * Allergies are medical records: deleting one only marks it deleted. Active
+ * allergies are also loaded with a visit and into document templates
+ * (`active_allergies`).
+ */
+
+use crate::{
+    db::rls::begin_with_rls,
+    models::{
+        AllergyStatus, AllergySummary, CreatePatientAllergyRequest, ListPatientAllergiesQuery,
+        PatientAllergy, PatientAllergyResponse, UpdatePatientAllergyRequest,
+    },
+    utils::{encryption::EncryptionKey, AppError, Result},
+};
+use sqlx::{PgConnection, PgPool};
+use tracing::info;
+use uuid::Uuid;
+
+const ALLERGY_SELECT: &str = r#"
+    SELECT id, patient_id, allergy_type, category, substance, reaction, severity, onset_date,
+           status, notes, created_by, created_at, updated_by, updated_at
+    FROM patient_allergies
+    WHERE deleted_at IS NULL
+"#;
+
+/// Most severe first, then most recently recorded
+const ALLERGY_ORDER: &str = r#"
+    ORDER BY CASE severity
+                 WHEN 'LIFE_THREATENING' THEN 0
+                 WHEN 'SEVERE' THEN 1
+                 WHEN 'MODERATE' THEN 2
+                 WHEN 'MILD' THEN 3
+                 ELSE 4
+             END,
+             created_at DESC
+"#;
+
+/// Patient allergy service
+pub struct PatientAllergyService {
+    pool: PgPool,
+    encryption_key: EncryptionKey,
+}
+
+impl PatientAllergyService {
+    /// Create a new patient allergy service
+    pub fn new(pool: PgPool, encryption_key: EncryptionKey) -> Self {
+        Self {
+            pool,
+            encryption_key,
+        }
+    }
+
+    /// Record an allergy
+    ///
+    /// Fails with Conflict if the patient is anonymized or merged.
+    pub async fn create_allergy(
+        &self,
+        patient_id: Uuid,
+        req: &CreatePatientAllergyRequest,
+        user_id: Uuid,
+    ) -> Result<PatientAllergyResponse> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        let patient: Option<(
+            Option<chrono::DateTime<chrono::Utc>>,
+            Option<chrono::DateTime<chrono::Utc>>,
+        )> = sqlx::query_as("SELECT anonymized_at, merged_at FROM patients WHERE id = $1")
+            .bind(patient_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+        match patient {
+            None => {
+                return Err(AppError::NotFound(format!(
+                    "Patient {} not found",
+                    patient_id
+                )))
+            }
+            Some((Some(_), _)) => {
+                return Err(AppError::Conflict(format!(
+                    "Patient {} has been anonymized",
+                    patient_id
+                )))
+            }
+            Some((_, Some(_))) => {
+                return Err(AppError::Conflict(format!(
+                    "Patient {} has been merged into another record",
+                    patient_id
+                )))
+            }
+            Some(_) => {}
+        }
+
+        let allergy = sqlx::query_as::<_, PatientAllergy>(
+            r#"
+            INSERT INTO patient_allergies (
+                patient_id, allergy_type, category, substance, reaction, severity,
+                onset_date, status, notes, created_by
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            RETURNING id, patient_id, allergy_type, category, substance, reaction, severity,
+                      onset_date, status, notes, created_by, created_at, updated_by, updated_at
+            "#,
+        )
+        .bind(patient_id)
+        .bind(req.allergy_type.as_str())
+        .bind(req.category.as_str())
+        .bind(self.encrypt(req.substance.trim())?)
+        .bind(self.encrypt_optional(&req.reaction)?)
+        .bind(req.severity.map(|s| s.as_str()))
+        .bind(req.onset_date)
+        .bind(req.status.unwrap_or(AllergyStatus::Active).as_str())
+        .bind(self.encrypt_optional(&req.notes)?)
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        info!(
+            "Allergy {} recorded for patient {} by {}",
+            allergy.id, patient_id, user_id
+        );
+
+        self.decrypt(&allergy)
+    }
+
+    /// List a patient's allergies, most severe first
+    pub async fn list_allergies(
+        &self,
+        patient_id: Uuid,
+        query: &ListPatientAllergiesQuery,
+        user_id: Uuid,
+    ) -> Result<Vec<PatientAllergyResponse>> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        let allergies = sqlx::query_as::<_, PatientAllergy>(&format!(
+            r#"
+            {}
+              AND patient_id = $1
+              AND ($2::TEXT IS NULL OR status = $2)
+              AND ($3::TEXT IS NULL OR category = $3)
+            {}
+            "#,
+            ALLERGY_SELECT, ALLERGY_ORDER
+        ))
+        .bind(patient_id)
+        .bind(query.status.map(|s| s.as_str()))
+        .bind(query.category.map(|c| c.as_str()))
+        .fetch_all(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        allergies.iter().map(|a| self.decrypt(a)).collect()
+    }
+
+    /// Get an allergy
+    pub async fn get_allergy(
+        &self,
+        patient_id: Uuid,
+        allergy_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<PatientAllergyResponse> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        let allergy = self.find(&mut tx, patient_id, allergy_id).await?;
+        tx.commit().await?;
+
+        self.decrypt(&allergy)
+    }
+
+    /// Update an allergy
+    pub async fn update_allergy(
+        &self,
+        patient_id: Uuid,
+        allergy_id: Uuid,
+        req: &UpdatePatientAllergyRequest,
+        user_id: Uuid,
+    ) -> Result<PatientAllergyResponse> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        let substance = req
+            .substance
+            .as_deref()
+            .map(|s| self.encrypt(s.trim()))
+            .transpose()?;
+
+        let updated = sqlx::query(
+            r#"
+            UPDATE patient_allergies
+            SET allergy_type = COALESCE($3, allergy_type),
+                category = COALESCE($4, category),
+                substance = COALESCE($5, substance),
+                reaction = COALESCE($6, reaction),
+                severity = COALESCE($7, severity),
+                onset_date = COALESCE($8, onset_date),
+                status = COALESCE($9, status),
+                notes = COALESCE($10, notes),
+                updated_by = $11
+            WHERE id = $1 AND patient_id = $2 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(allergy_id)
+        .bind(patient_id)
+        .bind(req.allergy_type.map(|t| t.as_str()))
+        .bind(req.category.map(|c| c.as_str()))
+        .bind(substance)
+        .bind(self.encrypt_optional(&req.reaction)?)
+        .bind(req.severity.map(|s| s.as_str()))
+        .bind(req.onset_date)
+        .bind(req.status.map(|s| s.as_str()))
+        .bind(self.encrypt_optional(&req.notes)?)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        if updated == 0 {
+            return Err(AppError::NotFound(format!(
+                "Allergy {} not found",
+                allergy_id
+            )));
+        }
+
+        let allergy = self.find(&mut tx, patient_id, allergy_id).await?;
+        tx.commit().await?;
+
+        self.decrypt(&allergy)
+    }
+
+    /// Mark an allergy deleted
+    pub async fn delete_allergy(
+        &self,
+        patient_id: Uuid,
+        allergy_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<()> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        let deleted = sqlx::query(
+            r#"
+            UPDATE patient_allergies
+            SET deleted_at = NOW(), deleted_by = $3, updated_by = $3
+            WHERE id = $1 AND patient_id = $2 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(allergy_id)
+        .bind(patient_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        if deleted == 0 {
+            return Err(AppError::NotFound(format!(
+                "Allergy {} not found",
+                allergy_id
+            )));
+        }
+
+        tx.commit().await?;
+
+        info!(
+            "Allergy {} of patient {} deleted by {}",
+            allergy_id, patient_id, user_id
+        );
+
+        Ok(())
+    }
+
+    /// Active allergies of a patient, most severe first
+    ///
+    /// Runs on a connection whose RLS context is already set, so visits and
+    /// documents can load them in their own transaction. Roles that cannot
+    /// read allergies get an empty list.
+    pub async fn active_allergies(
+        conn: &mut PgConnection,
+        encryption_key: &EncryptionKey,
+        patient_id: Uuid,
+    ) -> anyhow::Result<Vec<AllergySummary>> {
+        Ok(
+            Self::active_allergy_records(conn, encryption_key, patient_id)
+                .await?
+                .into_iter()
+                .map(AllergySummary::from)
+                .collect(),
+        )
+    }
+
+    /// Active allergies of a patient with their ids, most severe first
+    ///
+    /// Same rules as `active_allergies`; used where an alert has to point
+    /// back at the allergy record (prescription allergy checks).
+    pub async fn active_allergy_records(
+        conn: &mut PgConnection,
+        encryption_key: &EncryptionKey,
+        patient_id: Uuid,
+    ) -> anyhow::Result<Vec<PatientAllergyResponse>> {
+        let allergies = sqlx::query_as::<_, PatientAllergy>(&format!(
+            "{} AND patient_id = $1 AND status = $2 {}",
+            ALLERGY_SELECT, ALLERGY_ORDER
+        ))
+        .bind(patient_id)
+        .bind(AllergyStatus::Active.as_str())
+        .fetch_all(conn)
+        .await?;
+
+        allergies.iter().map(|a| a.decrypt(encryption_key)).collect()
+    }
+
+    async fn find(
+        &self,
+        tx: &mut PgConnection,
+        patient_id: Uuid,
+        allergy_id: Uuid,
+    ) -> Result<PatientAllergy> {
+        sqlx::query_as::<_, PatientAllergy>(&format!(
+            "{} AND id = $1 AND patient_id = $2",
+            ALLERGY_SELECT
+        ))
+        .bind(allergy_id)
+        .bind(patient_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Allergy {} not found", allergy_id)))
+    }
+
+    fn decrypt(&self, allergy: &PatientAllergy) -> Result<PatientAllergyResponse> {
+        allergy
+            .decrypt(&self.encryption_key)
+            .map_err(|e| AppError::Internal(format!("Failed to decrypt allergy: {}", e)))
+    }
+
+    fn encrypt(&self, value: &str) -> Result<String> {
+        self.encryption_key
+            .encrypt(value)
+            .map_err(|e| AppError::Internal(format!("Failed to encrypt allergy: {}", e)))
+    }
+
+    fn encrypt_optional(&self, value: &Option<String>) -> Result<Option<String>> {
+        value.as_deref().map(|v| self.encrypt(v)).transpose()
+    }
+}
@@ -0,0 +1,504 @@
+/*!
+ * Patient Attachment Service
+ *
+ * External documents on the patient record. The content is stored through
+ * `FileUploadService`, encrypted at rest; the metadata lives in
+ * `patient_attachments`, under RLS (doctors and nurses read and add,
+ * doctors edit and delete).
+ *
+ * Attachments are medical records: deleting one only marks it deleted and
+ * keeps the encrypted file.
+ *
+ * New attachments are queued for text recognition (`attachment_ocr_service`);
+ * the recognized text is searchable with `q` and returned, decrypted, with
+ * a single attachment.
+ */
+
+use crate::{
+    db::rls::begin_with_rls,
+    models::{
+        uploaded_file::FilePurpose, CreatePatientAttachmentRequest, ListPatientAttachmentsQuery,
+        OcrStatus, OcrSuggestions, PatientAttachment, PatientAttachmentResponse,
+        UpdatePatientAttachmentRequest,
+    },
+    services::FileUploadService,
+    utils::{encryption::EncryptionKey, AppError, Result},
+};
+use sqlx::{PgConnection, PgPool};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+const ATTACHMENT_SELECT: &str = r#"
+    SELECT a.id, a.patient_id, a.visit_id, a.file_id, a.category, a.title, a.description,
+           a.document_date, a.created_by, a.created_at, a.updated_by, a.updated_at,
+           a.ocr_status, a.ocr_text, a.ocr_suggestions,
+           f.original_filename, f.mime_type, f.file_size_bytes
+    FROM patient_attachments a
+    JOIN uploaded_files f ON f.id = a.file_id
+    WHERE a.deleted_at IS NULL
+"#;
+
+/// Patient attachment service
+pub struct PatientAttachmentService {
+    pool: PgPool,
+    encryption_key: EncryptionKey,
+}
+
+impl PatientAttachmentService {
+    /// Create a new patient attachment service
+    pub fn new(pool: PgPool, encryption_key: EncryptionKey) -> Self {
+        Self {
+            pool,
+            encryption_key,
+        }
+    }
+
+    /// Attach a document to a patient
+    ///
+    /// Fails with Validation if the file is not an allowed type (PDF, JPEG or
+    /// PNG, by magic bytes) or the visit is not one of the patient's, and
+    /// with Conflict if the patient is anonymized or merged.
+    pub async fn create_attachment(
+        &self,
+        patient_id: Uuid,
+        content: &[u8],
+        original_filename: &str,
+        req: &CreatePatientAttachmentRequest,
+        user_id: Uuid,
+    ) -> Result<PatientAttachmentResponse> {
+        let validation = FileUploadService::validate_file(
+            content,
+            original_filename,
+            FilePurpose::PatientAttachment,
+        );
+        if !validation.is_valid {
+            return Err(AppError::Validation(
+                validation
+                    .error_message
+                    .unwrap_or_else(|| "Invalid file".to_string()),
+            ));
+        }
+
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        let patient: Option<(
+            Option<chrono::DateTime<chrono::Utc>>,
+            Option<chrono::DateTime<chrono::Utc>>,
+        )> = sqlx::query_as("SELECT anonymized_at, merged_at FROM patients WHERE id = $1")
+            .bind(patient_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+        match patient {
+            None => {
+                return Err(AppError::NotFound(format!(
+                    "Patient {} not found",
+                    patient_id
+                )))
+            }
+            Some((Some(_), _)) => {
+                return Err(AppError::Conflict(format!(
+                    "Patient {} has been anonymized",
+                    patient_id
+                )))
+            }
+            Some((_, Some(_))) => {
+                return Err(AppError::Conflict(format!(
+                    "Patient {} has been merged into another record",
+                    patient_id
+                )))
+            }
+            Some(_) => {}
+        }
+
+        if let Some(visit_id) = req.visit_id {
+            self.check_visit(&mut tx, patient_id, visit_id).await?;
+        }
+
+        let file = FileUploadService::upload_encrypted_file(
+            &self.pool,
+            content,
+            original_filename,
+            FilePurpose::PatientAttachment,
+            None,
+            user_id,
+            &self.encryption_key,
+        )
+        .await
+        .map_err(|e| {
+            warn!(
+                "Failed to store attachment of patient {}: {}",
+                patient_id, e
+            );
+            AppError::Internal("Failed to store attachment".to_string())
+        })?;
+
+        let created = async {
+            let id: Uuid = sqlx::query_scalar(
+                r#"
+                INSERT INTO patient_attachments (
+                    patient_id, visit_id, file_id, category, title, description,
+                    document_date, created_by
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                RETURNING id
+                "#,
+            )
+            .bind(patient_id)
+            .bind(req.visit_id)
+            .bind(file.id)
+            .bind(req.category.as_str())
+            .bind(req.title.trim())
+            .bind(&req.description)
+            .bind(req.document_date)
+            .bind(user_id)
+            .fetch_one(&mut *tx)
+            .await?;
+
+            let attachment = sqlx::query_as::<_, PatientAttachment>(&format!(
+                "{} AND a.id = $1",
+                ATTACHMENT_SELECT
+            ))
+            .bind(id)
+            .fetch_one(&mut *tx)
+            .await?;
+
+            tx.commit().await?;
+            Ok::<_, sqlx::Error>(attachment)
+        }
+        .await;
+
+        let attachment = match created {
+            Ok(attachment) => attachment,
+            Err(e) => {
+                if let Err(cleanup) = FileUploadService::hard_delete_file(&self.pool, file.id).await
+                {
+                    warn!(
+                        "Failed to remove orphaned attachment file {}: {}",
+                        file.id, cleanup
+                    );
+                }
+                return Err(e.into());
+            }
+        };
+
+        info!(
+            "Attachment {} added to patient {} by {}",
+            attachment.id, patient_id, user_id
+        );
+
+        Ok(self.to_response(attachment, false))
+    }
+
+    /// List a patient's attachments, newest document first
+    pub async fn list_attachments(
+        &self,
+        patient_id: Uuid,
+        query: &ListPatientAttachmentsQuery,
+        user_id: Uuid,
+    ) -> Result<Vec<PatientAttachmentResponse>> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        let attachments = sqlx::query_as::<_, PatientAttachment>(&format!(
+            r#"
+            {}
+              AND a.patient_id = $1
+              AND ($2::TEXT IS NULL OR a.category = $2)
+              AND ($3::UUID IS NULL OR a.visit_id = $3)
+            ORDER BY a.document_date DESC NULLS LAST, a.created_at DESC
+            "#,
+            ATTACHMENT_SELECT
+        ))
+        .bind(patient_id)
+        .bind(query.category.map(|c| c.as_str()))
+        .bind(query.visit_id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        // The recognized text is encrypted, so the search runs here
+        let search = query
+            .q
+            .as_deref()
+            .map(|q| q.trim().to_lowercase())
+            .filter(|q| !q.is_empty());
+
+        Ok(attachments
+            .into_iter()
+            .filter(|attachment| match &search {
+                Some(search) => self.matches_search(attachment, search),
+                None => true,
+            })
+            .map(|attachment| self.to_response(attachment, false))
+            .collect())
+    }
+
+    /// Get an attachment's metadata
+    pub async fn get_attachment(
+        &self,
+        patient_id: Uuid,
+        attachment_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<PatientAttachmentResponse> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        let attachment = self.find(&mut tx, patient_id, attachment_id).await?;
+        tx.commit().await?;
+
+        Ok(self.to_response(attachment, true))
+    }
+
+    /// Get an attachment with its decrypted content
+    pub async fn download_attachment(
+        &self,
+        patient_id: Uuid,
+        attachment_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<(PatientAttachment, Vec<u8>)> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        let attachment = self.find(&mut tx, patient_id, attachment_id).await?;
+        tx.commit().await?;
+
+        let (_, content) = FileUploadService::get_decrypted_file_with_content(
+            &self.pool,
+            attachment.file_id,
+            &self.encryption_key,
+        )
+        .await
+        .map_err(|e| {
+            warn!("Failed to read attachment {}: {}", attachment_id, e);
+            AppError::Internal("Failed to read attachment".to_string())
+        })?
+        .ok_or_else(|| {
+            warn!("File of attachment {} is missing", attachment_id);
+            AppError::Internal("Failed to read attachment".to_string())
+        })?;
+
+        Ok((attachment, content))
+    }
+
+    /// Update an attachment's metadata
+    pub async fn update_attachment(
+        &self,
+        patient_id: Uuid,
+        attachment_id: Uuid,
+        req: &UpdatePatientAttachmentRequest,
+        user_id: Uuid,
+    ) -> Result<PatientAttachmentResponse> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        if let Some(visit_id) = req.visit_id {
+            self.check_visit(&mut tx, patient_id, visit_id).await?;
+        }
+
+        let updated = sqlx::query(
+            r#"
+            UPDATE patient_attachments
+            SET category = COALESCE($3, category),
+                title = COALESCE($4, title),
+                description = COALESCE($5, description),
+                document_date = COALESCE($6, document_date),
+                visit_id = COALESCE($7, visit_id),
+                updated_by = $8
+            WHERE id = $1 AND patient_id = $2 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(attachment_id)
+        .bind(patient_id)
+        .bind(req.category.map(|c| c.as_str()))
+        .bind(req.title.as_deref().map(str::trim))
+        .bind(&req.description)
+        .bind(req.document_date)
+        .bind(req.visit_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        if updated == 0 {
+            return Err(AppError::NotFound(format!(
+                "Attachment {} not found",
+                attachment_id
+            )));
+        }
+
+        let attachment = self.find(&mut tx, patient_id, attachment_id).await?;
+        tx.commit().await?;
+
+        Ok(self.to_response(attachment, false))
+    }
+
+    /// Queue an attachment for text recognition again
+    ///
+    /// Clears the previous result and attempts; the OCR job picks it up on
+    /// its next run.
+    pub async fn requeue_ocr(
+        &self,
+        patient_id: Uuid,
+        attachment_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<PatientAttachmentResponse> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        let updated = sqlx::query(
+            r#"
+            UPDATE patient_attachments
+            SET ocr_status = $3, ocr_text = NULL, ocr_suggestions = NULL, ocr_attempts = 0,
+                ocr_error = NULL, ocr_started_at = NULL, ocr_completed_at = NULL
+            WHERE id = $1 AND patient_id = $2 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(attachment_id)
+        .bind(patient_id)
+        .bind(OcrStatus::Pending.as_str())
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        if updated == 0 {
+            return Err(AppError::NotFound(format!(
+                "Attachment {} not found",
+                attachment_id
+            )));
+        }
+
+        let attachment = self.find(&mut tx, patient_id, attachment_id).await?;
+        tx.commit().await?;
+
+        Ok(self.to_response(attachment, false))
+    }
+
+    /// Mark an attachment deleted (the encrypted file is kept)
+    pub async fn delete_attachment(
+        &self,
+        patient_id: Uuid,
+        attachment_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<()> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        let deleted = sqlx::query(
+            r#"
+            UPDATE patient_attachments
+            SET deleted_at = NOW(), deleted_by = $3, updated_by = $3
+            WHERE id = $1 AND patient_id = $2 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(attachment_id)
+        .bind(patient_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        if deleted == 0 {
+            return Err(AppError::NotFound(format!(
+                "Attachment {} not found",
+                attachment_id
+            )));
+        }
+
+        tx.commit().await?;
+
+        info!(
+            "Attachment {} of patient {} deleted by {}",
+            attachment_id, patient_id, user_id
+        );
+
+        Ok(())
+    }
+
+    /// Response with the OCR fields decrypted (the text only if `with_text`)
+    fn to_response(
+        &self,
+        attachment: PatientAttachment,
+        with_text: bool,
+    ) -> PatientAttachmentResponse {
+        let suggestions = attachment.ocr_suggestions.as_deref().and_then(|encrypted| {
+            self.encryption_key
+                .decrypt_json::<OcrSuggestions>(encrypted)
+                .map_err(|e| {
+                    warn!(
+                        "Failed to decrypt OCR suggestions of attachment {}: {}",
+                        attachment.id, e
+                    )
+                })
+                .ok()
+        });
+        let text = if with_text {
+            self.decrypt_ocr_text(&attachment)
+        } else {
+            None
+        };
+
+        let mut response = PatientAttachmentResponse::from(attachment);
+        response.ocr_suggestions = suggestions;
+        response.ocr_text = text;
+        response
+    }
+
+    fn decrypt_ocr_text(&self, attachment: &PatientAttachment) -> Option<String> {
+        let encrypted = attachment.ocr_text.as_deref()?;
+        self.encryption_key
+            .decrypt(encrypted)
+            .map_err(|e| {
+                warn!(
+                    "Failed to decrypt OCR text of attachment {}: {}",
+                    attachment.id, e
+                )
+            })
+            .ok()
+    }
+
+    /// Case-insensitive match of `search` (already lowercase)
+    fn matches_search(&self, attachment: &PatientAttachment, search: &str) -> bool {
+        attachment.title.to_lowercase().contains(search)
+            || attachment
+                .description
+                .as_deref()
+                .is_some_and(|d| d.to_lowercase().contains(search))
+            || self
+                .decrypt_ocr_text(attachment)
+                .is_some_and(|text| text.to_lowercase().contains(search))
+    }
+
+    async fn find(
+        &self,
+        tx: &mut PgConnection,
+        patient_id: Uuid,
+        attachment_id: Uuid,
+    ) -> Result<PatientAttachment> {
+        sqlx::query_as::<_, PatientAttachment>(&format!(
+            "{} AND a.id = $1 AND a.patient_id = $2",
+            ATTACHMENT_SELECT
+        ))
+        .bind(attachment_id)
+        .bind(patient_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Attachment {} not found", attachment_id)))
+    }
+
+    /// The visit must be one of the patient's, visible to the user
+    async fn check_visit(
+        &self,
+        tx: &mut PgConnection,
+        patient_id: Uuid,
+        visit_id: Uuid,
+    ) -> Result<()> {
+        let exists: bool = sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM visits WHERE id = $1 AND patient_id = $2)",
+        )
+        .bind(visit_id)
+        .bind(patient_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if !exists {
+            return Err(AppError::Validation(format!(
+                "Visit {} is not a visit of this patient",
+                visit_id
+            )));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,195 @@
+/*!
+ * Patient Chart Service
+ *
+ * Builds the aggregated patient chart from the services behind each panel
+ * (visits, prescriptions, problem list, appointments, vitals, documents),
+ * querying them concurrently. Every section runs under the caller's RLS
+ * context, so the chart shows exactly what the individual endpoints would.
+ */
+
+use crate::{
+    db::rls::begin_with_rls,
+    models::{
+        GeneratedDocumentFilter, ListPatientProblemsQuery, Patient, PatientChartQuery,
+        PatientChartResponse, PatientChartSections, UserRole, DEFAULT_CHART_SECTION_LIMIT,
+    },
+    services::{
+        AppointmentService, DocumentService, PatientProblemService, PrescriptionService,
+        VisitService, VitalsService,
+    },
+    utils::{encryption::EncryptionKey, AppError, Result},
+};
+use sqlx::PgPool;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+/// Patient chart service
+pub struct PatientChartService {
+    pool: PgPool,
+    encryption_key: EncryptionKey,
+    document_storage_path: PathBuf,
+}
+
+impl PatientChartService {
+    /// Create a new patient chart service
+    pub fn new(
+        pool: PgPool,
+        encryption_key: EncryptionKey,
+        document_storage_path: PathBuf,
+    ) -> Self {
+        Self {
+            pool,
+            encryption_key,
+            document_storage_path,
+        }
+    }
+
+    /// Chart of a patient, with only the `sections` the caller may read
+    ///
+    /// Returns `NotFound` if the patient does not exist or is not visible
+    /// to the user.
+    pub async fn get_chart(
+        &self,
+        patient_id: Uuid,
+        query: &PatientChartQuery,
+        sections: PatientChartSections,
+        user_id: Uuid,
+        user_role: &UserRole,
+    ) -> Result<PatientChartResponse> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        let patient = Patient::find_by_id(&mut *tx, patient_id)
+            .await
+            .map_err(internal("Failed to get patient"))?
+            .ok_or_else(|| AppError::NotFound(format!("Patient {} not found", patient_id)))?;
+        tx.commit().await?;
+
+        let patient = patient
+            .decrypt(&self.encryption_key)
+            .map_err(internal("Failed to decrypt patient"))?;
+
+        let pool = &self.pool;
+        let key = &self.encryption_key;
+        let role = user_role.to_string();
+
+        let recent_visits = async {
+            if !sections.visits {
+                return Ok(None);
+            }
+            VisitService::new(pool.clone(), key.clone())
+                .get_patient_visits(
+                    patient_id,
+                    user_id,
+                    Some(query.visits_limit.unwrap_or(DEFAULT_CHART_SECTION_LIMIT)),
+                    None,
+                )
+                .await
+                .map(Some)
+                .map_err(internal("Failed to get visits"))
+        };
+
+        let latest_vitals = async {
+            if !sections.visits {
+                return Ok(None);
+            }
+            VitalsService::new(pool.clone(), key.clone())
+                .get_latest(patient_id, user_id)
+                .await
+        };
+
+        let active_prescriptions = async {
+            if !sections.prescriptions {
+                return Ok(None);
+            }
+            PrescriptionService::new(pool.clone(), key.clone())
+                .get_patient_prescriptions(patient_id, true, user_id, &role)
+                .await
+                .map(Some)
+                .map_err(internal("Failed to get prescriptions"))
+        };
+
+        let problems = async {
+            if !sections.problems {
+                return Ok(None);
+            }
+            PatientProblemService::new(pool.clone(), key.clone())
+                .list_problems(patient_id, &ListPatientProblemsQuery::default(), user_id)
+                .await
+                .map(Some)
+        };
+
+        let upcoming_appointments = async {
+            if !sections.appointments {
+                return Ok(None);
+            }
+            AppointmentService::new(pool.clone(), key.clone())
+                .get_upcoming_for_patient(
+                    patient_id,
+                    query
+                        .appointments_limit
+                        .unwrap_or(DEFAULT_CHART_SECTION_LIMIT),
+                    user_id,
+                )
+                .await
+                .map(Some)
+                .map_err(internal("Failed to get appointments"))
+        };
+
+        let recent_documents = async {
+            if !sections.documents {
+                return Ok(None);
+            }
+            let filter = GeneratedDocumentFilter {
+                patient_id: Some(patient_id),
+                ..Default::default()
+            };
+            DocumentService::new(
+                pool.clone(),
+                key.clone(),
+                self.document_storage_path.clone(),
+            )
+            .list_documents(
+                filter,
+                query.documents_limit.unwrap_or(DEFAULT_CHART_SECTION_LIMIT),
+                0,
+                user_id,
+            )
+            .await
+            .map(|list| Some(list.documents))
+            .map_err(internal("Failed to get documents"))
+        };
+
+        let (
+            recent_visits,
+            latest_vitals,
+            active_prescriptions,
+            problems,
+            upcoming_appointments,
+            recent_documents,
+        ) = tokio::try_join!(
+            recent_visits,
+            latest_vitals,
+            active_prescriptions,
+            problems,
+            upcoming_appointments,
+            recent_documents,
+        )?;
+
+        Ok(PatientChartResponse {
+            patient,
+            recent_visits,
+            active_prescriptions,
+            problems,
+            upcoming_appointments,
+            latest_vitals,
+            recent_documents,
+        })
+    }
+}
+
+/// Map a section's anyhow error to `AppError::Internal`, logging the cause
+fn internal(context: &'static str) -> impl Fn(anyhow::Error) -> AppError {
+    move |e| {
+        tracing::error!("{}: {:#}", context, e);
+        AppError::Internal(context.to_string())
+    }
+}
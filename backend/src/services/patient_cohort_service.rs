@@ -0,0 +1,462 @@
+/*!
+ * Patient Cohort Service
+ *
+ * Saves cohort criteria and selects the patients matching them. Diagnoses
+ * and the last visit are matched in SQL; dates of birth and medication names
+ * are encrypted, so the age range and the medication criterion are applied
+ * after decrypting the remaining candidates. Queries run with the caller's
+ * RLS context, so a cohort only contains patients the caller may see.
+ *
+ * The patients of a cohort can be sent a notification campaign (one queued
+ * notification each); bulk document generation goes through
+ * `DocumentService::bulk_generate` with the cohort's patient ids.
+ */
+
+use crate::db::rls::begin_with_rls;
+use crate::models::{
+    CohortCampaignSkip, CohortCriteria, CohortMember, CohortNotificationRequest,
+    CohortNotificationResult, CohortPreview, CreateNotificationRequest, CreatePatientCohortRequest,
+    PatientCohort, PatientCohortResponse, UpdatePatientCohortRequest, COHORT_PREVIEW_LIMIT,
+    MAX_CAMPAIGN_RECIPIENTS,
+};
+use crate::services::NotificationService;
+use crate::utils::{AppError, EncryptionKey, Result};
+use chrono::{NaiveDate, Utc};
+use sqlx::PgPool;
+use std::collections::HashSet;
+use uuid::Uuid;
+
+const COHORT_COLUMNS: &str = "id, name, description, criteria, created_by, created_at, updated_at";
+
+/// Candidate row: id, medical record number, encrypted first name, last
+/// name, date of birth and email, last visit date
+type CandidateRow = (
+    Uuid,
+    String,
+    String,
+    String,
+    String,
+    Option<String>,
+    Option<NaiveDate>,
+);
+
+/// A selected patient with the encrypted email kept for campaigns
+pub(crate) struct SelectedPatient {
+    pub(crate) member: CohortMember,
+    pub(crate) email: Option<String>,
+}
+
+/// Patients of a cohort
+pub(crate) struct Selection {
+    pub(crate) patients: Vec<SelectedPatient>,
+    /// Candidates skipped because their data could not be decrypted
+    pub(crate) unreadable: Vec<Uuid>,
+}
+
+/// Patient cohort service
+pub struct PatientCohortService {
+    pool: PgPool,
+    encryption_key: EncryptionKey,
+}
+
+impl PatientCohortService {
+    /// Create a new patient cohort service
+    pub fn new(pool: PgPool, encryption_key: EncryptionKey) -> Self {
+        Self {
+            pool,
+            encryption_key,
+        }
+    }
+
+    // ==================== Saved Cohorts ====================
+
+    /// List cohorts: every one for administrators, otherwise the caller's own
+    pub async fn list(&self, user_id: Uuid, is_admin: bool) -> Result<Vec<PatientCohortResponse>> {
+        let rows = sqlx::query_as::<_, PatientCohort>(&format!(
+            "SELECT {} FROM patient_cohorts WHERE $1 OR created_by = $2 ORDER BY name, created_at",
+            COHORT_COLUMNS
+        ))
+        .bind(is_admin)
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(to_response).collect()
+    }
+
+    /// Get a cohort the caller may see
+    pub async fn get(
+        &self,
+        cohort_id: Uuid,
+        user_id: Uuid,
+        is_admin: bool,
+    ) -> Result<PatientCohortResponse> {
+        to_response(self.get_row(cohort_id, user_id, is_admin).await?)
+    }
+
+    /// Save a cohort owned by `created_by`
+    pub async fn create(
+        &self,
+        req: &CreatePatientCohortRequest,
+        created_by: Uuid,
+    ) -> Result<PatientCohortResponse> {
+        let row = sqlx::query_as::<_, PatientCohort>(&format!(
+            r#"
+            INSERT INTO patient_cohorts (name, description, criteria, created_by)
+            VALUES ($1, $2, $3, $4)
+            RETURNING {}
+            "#,
+            COHORT_COLUMNS
+        ))
+        .bind(req.name.trim())
+        .bind(&req.description)
+        .bind(serde_json::to_value(&req.criteria).unwrap_or_default())
+        .bind(created_by)
+        .fetch_one(&self.pool)
+        .await?;
+
+        to_response(row)
+    }
+
+    /// Update a cohort the caller may see
+    pub async fn update(
+        &self,
+        cohort_id: Uuid,
+        req: &UpdatePatientCohortRequest,
+        user_id: Uuid,
+        is_admin: bool,
+    ) -> Result<PatientCohortResponse> {
+        self.get_row(cohort_id, user_id, is_admin).await?;
+
+        let criteria = req
+            .criteria
+            .as_ref()
+            .map(|criteria| serde_json::to_value(criteria).unwrap_or_default());
+
+        let row = sqlx::query_as::<_, PatientCohort>(&format!(
+            r#"
+            UPDATE patient_cohorts
+            SET name = COALESCE($2, name),
+                description = COALESCE($3, description),
+                criteria = COALESCE($4, criteria),
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING {}
+            "#,
+            COHORT_COLUMNS
+        ))
+        .bind(cohort_id)
+        .bind(req.name.as_deref().map(str::trim))
+        .bind(&req.description)
+        .bind(criteria)
+        .fetch_one(&self.pool)
+        .await?;
+
+        to_response(row)
+    }
+
+    /// Delete a cohort the caller may see
+    pub async fn delete(&self, cohort_id: Uuid, user_id: Uuid, is_admin: bool) -> Result<()> {
+        self.get_row(cohort_id, user_id, is_admin).await?;
+
+        sqlx::query("DELETE FROM patient_cohorts WHERE id = $1")
+            .bind(cohort_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_row(
+        &self,
+        cohort_id: Uuid,
+        user_id: Uuid,
+        is_admin: bool,
+    ) -> Result<PatientCohort> {
+        sqlx::query_as::<_, PatientCohort>(&format!(
+            "SELECT {} FROM patient_cohorts WHERE id = $1 AND ($2 OR created_by = $3)",
+            COHORT_COLUMNS
+        ))
+        .bind(cohort_id)
+        .bind(is_admin)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Cohort {} not found", cohort_id)))
+    }
+
+    // ==================== Selection ====================
+
+    /// Count the patients matching the criteria and list the first ones
+    pub async fn preview(&self, criteria: &CohortCriteria, user_id: Uuid) -> Result<CohortPreview> {
+        let selection = self.select(criteria, user_id).await?;
+
+        Ok(CohortPreview {
+            count: selection.patients.len(),
+            patients: selection
+                .patients
+                .into_iter()
+                .take(COHORT_PREVIEW_LIMIT)
+                .map(|patient| patient.member)
+                .collect(),
+            unreadable_records: selection.unreadable.len(),
+        })
+    }
+
+    /// Ids of the patients matching the criteria
+    pub async fn patient_ids(&self, criteria: &CohortCriteria, user_id: Uuid) -> Result<Vec<Uuid>> {
+        let selection = self.select(criteria, user_id).await?;
+
+        Ok(selection
+            .patients
+            .iter()
+            .map(|patient| patient.member.patient_id)
+            .collect())
+    }
+
+    /// Select the patients matching the criteria, by medical record number
+    pub(crate) async fn select(
+        &self,
+        criteria: &CohortCriteria,
+        user_id: Uuid,
+    ) -> Result<Selection> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        // Plain-text criteria; codes were validated, so the patterns hold no
+        // LIKE wildcards other than the trailing one
+        let candidates: Vec<CandidateRow> = sqlx::query_as(
+            r#"
+            SELECT p.id, p.medical_record_number, p.first_name, p.last_name,
+                   p.date_of_birth, p.email, lv.last_visit_date
+            FROM patients p
+            LEFT JOIN LATERAL (
+                SELECT MAX(v.visit_date) AS last_visit_date
+                FROM visits v
+                WHERE v.patient_id = p.id AND v.deleted_at IS NULL
+            ) lv ON TRUE
+            WHERE p.status = 'ACTIVE'
+              AND p.deleted_at IS NULL
+              AND p.anonymized_at IS NULL
+              AND p.merged_at IS NULL
+              AND ($1::DATE IS NULL OR lv.last_visit_date < $1)
+              AND (
+                cardinality($2::TEXT[]) = 0
+                OR EXISTS (
+                    SELECT 1
+                    FROM visit_diagnoses vd
+                    JOIN visits v ON vd.visit_id = v.id
+                    WHERE v.patient_id = p.id
+                      AND v.deleted_at IS NULL
+                      AND UPPER(vd.icd10_code) LIKE ANY($2)
+                )
+                OR EXISTS (
+                    SELECT 1
+                    FROM patient_problems pp
+                    WHERE pp.patient_id = p.id
+                      AND pp.status = 'ACTIVE'
+                      AND pp.deleted_at IS NULL
+                      AND UPPER(pp.icd10_code) LIKE ANY($2)
+                )
+              )
+            ORDER BY p.medical_record_number
+            "#,
+        )
+        .bind(criteria.last_visit_before)
+        .bind(criteria.diagnosis_patterns())
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let on_medication = if criteria.medications.is_empty() {
+            None
+        } else {
+            let candidate_ids: Vec<Uuid> = candidates.iter().map(|row| row.0).collect();
+            let prescriptions: Vec<(Uuid, String, Option<String>)> = sqlx::query_as(
+                r#"
+                SELECT patient_id, medication_name, generic_name
+                FROM prescriptions
+                WHERE status = 'ACTIVE' AND deleted_at IS NULL AND patient_id = ANY($1)
+                "#,
+            )
+            .bind(&candidate_ids)
+            .fetch_all(&mut *tx)
+            .await?;
+
+            let mut matching = HashSet::new();
+            for (patient_id, medication_name, generic_name) in prescriptions {
+                let Ok(medication_name) = self.encryption_key.decrypt(&medication_name) else {
+                    continue;
+                };
+                let generic_name =
+                    generic_name.and_then(|name| self.encryption_key.decrypt(&name).ok());
+                if criteria.matches_medication(&medication_name, generic_name.as_deref()) {
+                    matching.insert(patient_id);
+                }
+            }
+            Some(matching)
+        };
+
+        tx.commit().await?;
+
+        let has_age_range = criteria.min_age.is_some() || criteria.max_age.is_some();
+        let today = Utc::now().date_naive();
+        let mut patients = Vec::new();
+        let mut unreadable = Vec::new();
+
+        for (
+            id,
+            medical_record_number,
+            first_name,
+            last_name,
+            date_of_birth,
+            email,
+            last_visit_date,
+        ) in candidates
+        {
+            if on_medication
+                .as_ref()
+                .is_some_and(|matching| !matching.contains(&id))
+            {
+                continue;
+            }
+
+            let age = self
+                .encryption_key
+                .decrypt(&date_of_birth)
+                .ok()
+                .and_then(|dob| NaiveDate::parse_from_str(dob.trim(), "%Y-%m-%d").ok())
+                .map(|dob| today.years_since(dob).unwrap_or(0));
+            if has_age_range {
+                match age {
+                    Some(age) if criteria.matches_age(age) => {}
+                    Some(_) => continue,
+                    None => {
+                        unreadable.push(id);
+                        continue;
+                    }
+                }
+            }
+
+            let (Ok(first_name), Ok(last_name)) = (
+                self.encryption_key.decrypt(&first_name),
+                self.encryption_key.decrypt(&last_name),
+            ) else {
+                unreadable.push(id);
+                continue;
+            };
+
+            patients.push(SelectedPatient {
+                member: CohortMember {
+                    patient_id: id,
+                    medical_record_number,
+                    first_name,
+                    last_name,
+                    age,
+                    last_visit_date,
+                },
+                email,
+            });
+        }
+
+        Ok(Selection {
+            patients,
+            unreadable,
+        })
+    }
+
+    // ==================== Campaigns ====================
+
+    /// Queue one notification for each patient of the cohort
+    ///
+    /// Patients without an email address, who disabled email notifications
+    /// or (for MARKETING campaigns) have no active MARKETING consent are
+    /// skipped and listed in the result.
+    pub async fn queue_notifications(
+        &self,
+        criteria: &CohortCriteria,
+        req: &CohortNotificationRequest,
+        notification_service: &NotificationService,
+        user_id: Uuid,
+    ) -> Result<CohortNotificationResult> {
+        let selection = self.select(criteria, user_id).await?;
+        let total = selection.patients.len();
+        if total > MAX_CAMPAIGN_RECIPIENTS {
+            return Err(AppError::Validation(format!(
+                "The cohort has {} patients; a campaign can reach at most {}",
+                total, MAX_CAMPAIGN_RECIPIENTS
+            )));
+        }
+
+        let notification_type = req.notification_type.notification_type().as_str();
+        let mut queued = 0;
+        let mut skipped = Vec::new();
+
+        for patient in selection.patients {
+            let patient_id = patient.member.patient_id;
+            let skip = |reason: &str| CohortCampaignSkip {
+                patient_id,
+                reason: reason.to_string(),
+            };
+
+            let email = patient
+                .email
+                .as_deref()
+                .and_then(|email| self.encryption_key.decrypt(email).ok())
+                .filter(|email| !email.trim().is_empty());
+            let Some(email) = email else {
+                skipped.push(skip("No email address"));
+                continue;
+            };
+            if !notification_service
+                .can_patient_receive_email(patient_id, user_id)
+                .await
+            {
+                skipped.push(skip("Email notifications disabled"));
+                continue;
+            }
+
+            let request = CreateNotificationRequest {
+                patient_id: Some(patient_id),
+                appointment_id: None,
+                notification_type: notification_type.to_string(),
+                delivery_method: "EMAIL".to_string(),
+                recipient_email: Some(email),
+                recipient_name: Some(format!(
+                    "{} {}",
+                    patient.member.first_name, patient.member.last_name
+                )),
+                subject: Some(req.subject.clone()),
+                message_body: req.message_body.clone(),
+                scheduled_for: req.scheduled_for,
+                priority: Some(5),
+                metadata: Some(serde_json::json!({ "campaign": true })),
+            };
+            match notification_service
+                .create_notification(request, user_id)
+                .await
+            {
+                Ok(_) => queued += 1,
+                Err(e) if e.to_string().contains("MARKETING consent") => {
+                    skipped.push(skip("No active MARKETING consent"))
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        "Failed to queue campaign notification for {}: {}",
+                        patient_id,
+                        e
+                    );
+                    skipped.push(skip("Notification could not be queued"));
+                }
+            }
+        }
+
+        Ok(CohortNotificationResult {
+            total,
+            queued,
+            skipped,
+        })
+    }
+}
+
+fn to_response(row: PatientCohort) -> Result<PatientCohortResponse> {
+    PatientCohortResponse::try_from(row).map_err(AppError::Internal)
+}
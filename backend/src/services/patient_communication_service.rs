@@ -0,0 +1,445 @@
+/*!
+ * Patient Communication Service
+ *
+ * Per-patient communication log, under RLS (all staff read, record and
+ * annotate). Sent notifications and delivered documents are logged by
+ * `record_notification` and `record_document`, called by the notification
+ * and document services once the delivery is committed; like audit logging,
+ * a failure there is only logged and does not undo the delivery.
+ *
+ * Entries are never edited or deleted by staff; annotations are added
+ * instead.
+ */
+
+use crate::{
+    db::rls::begin_with_rls,
+    models::{
+        CommunicationAnnotation, CommunicationAnnotationResponse, CommunicationChannel,
+        CommunicationSource, CreateCommunicationAnnotationRequest,
+        CreatePatientCommunicationRequest, ListPatientCommunicationsQuery,
+        ListPatientCommunicationsResponse, PatientCommunication, PatientCommunicationResponse,
+    },
+    utils::{encryption::EncryptionKey, AppError, Result},
+};
+use chrono::{Duration, Utc};
+use sqlx::{PgConnection, PgPool};
+use tracing::info;
+use uuid::Uuid;
+
+const COMMUNICATION_COLUMNS: &str = r#"
+    id, patient_id, source, channel, direction, notification_id, document_id,
+    recipient, subject, notes, occurred_at, created_by, created_at
+"#;
+
+/// Clock skew tolerated on the time of a manually recorded contact
+const MAX_FUTURE_SKEW_MINUTES: i64 = 5;
+
+/// Patient communication service
+pub struct PatientCommunicationService {
+    pool: PgPool,
+    encryption_key: EncryptionKey,
+}
+
+impl PatientCommunicationService {
+    /// Create a new patient communication service
+    pub fn new(pool: PgPool, encryption_key: EncryptionKey) -> Self {
+        Self {
+            pool,
+            encryption_key,
+        }
+    }
+
+    /// List a patient's communication log, most recent first
+    pub async fn list(
+        &self,
+        patient_id: Uuid,
+        query: &ListPatientCommunicationsQuery,
+        user_id: Uuid,
+    ) -> Result<ListPatientCommunicationsResponse> {
+        let limit = query.limit();
+        let offset = query.offset();
+        let source = query.source.map(|s| s.as_str());
+        let channel = query.channel.map(|c| c.as_str());
+
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        let entries = sqlx::query_as::<_, PatientCommunication>(&format!(
+            r#"
+            SELECT {}
+            FROM patient_communications
+            WHERE patient_id = $1
+              AND ($2::TEXT IS NULL OR source = $2)
+              AND ($3::TEXT IS NULL OR channel = $3)
+              AND ($4::TIMESTAMPTZ IS NULL OR occurred_at >= $4)
+              AND ($5::TIMESTAMPTZ IS NULL OR occurred_at < $5)
+            ORDER BY occurred_at DESC, created_at DESC
+            LIMIT $6 OFFSET $7
+            "#,
+            COMMUNICATION_COLUMNS
+        ))
+        .bind(patient_id)
+        .bind(source)
+        .bind(channel)
+        .bind(query.from)
+        .bind(query.to)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let total: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*)
+            FROM patient_communications
+            WHERE patient_id = $1
+              AND ($2::TEXT IS NULL OR source = $2)
+              AND ($3::TEXT IS NULL OR channel = $3)
+              AND ($4::TIMESTAMPTZ IS NULL OR occurred_at >= $4)
+              AND ($5::TIMESTAMPTZ IS NULL OR occurred_at < $5)
+            "#,
+        )
+        .bind(patient_id)
+        .bind(source)
+        .bind(channel)
+        .bind(query.from)
+        .bind(query.to)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let ids: Vec<Uuid> = entries.iter().map(|e| e.id).collect();
+        let annotations = self.annotations(&mut tx, &ids).await?;
+        tx.commit().await?;
+
+        let communications = entries
+            .iter()
+            .map(|entry| {
+                let entry_annotations = annotations
+                    .iter()
+                    .filter(|a| a.communication_id == entry.id)
+                    .cloned()
+                    .collect();
+                self.decrypt(entry, entry_annotations)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(ListPatientCommunicationsResponse {
+            communications,
+            total,
+            limit,
+            offset,
+        })
+    }
+
+    /// Get a log entry with its annotations
+    pub async fn get(
+        &self,
+        patient_id: Uuid,
+        communication_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<PatientCommunicationResponse> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        let entry = self.find(&mut tx, patient_id, communication_id).await?;
+        let annotations = self.annotations(&mut tx, &[entry.id]).await?;
+        tx.commit().await?;
+
+        self.decrypt(&entry, annotations)
+    }
+
+    /// Record a contact by hand (phone call, letter, visit at the desk...)
+    ///
+    /// Fails with Validation for a time in the future, and with Conflict if
+    /// the patient is anonymized or merged.
+    pub async fn create(
+        &self,
+        patient_id: Uuid,
+        req: &CreatePatientCommunicationRequest,
+        user_id: Uuid,
+    ) -> Result<PatientCommunicationResponse> {
+        let occurred_at = req.occurred_at.unwrap_or_else(Utc::now);
+        if occurred_at > Utc::now() + Duration::minutes(MAX_FUTURE_SKEW_MINUTES) {
+            return Err(AppError::Validation(
+                "Communication time cannot be in the future".to_string(),
+            ));
+        }
+
+        let notes = self
+            .encryption_key
+            .encrypt(req.notes.trim())
+            .map_err(|e| AppError::Internal(format!("Failed to encrypt notes: {}", e)))?;
+
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        self.check_patient(&mut tx, patient_id).await?;
+
+        let entry = sqlx::query_as::<_, PatientCommunication>(&format!(
+            r#"
+            INSERT INTO patient_communications (
+                patient_id, source, channel, direction, recipient, subject, notes,
+                occurred_at, created_by
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            RETURNING {}
+            "#,
+            COMMUNICATION_COLUMNS
+        ))
+        .bind(patient_id)
+        .bind(CommunicationSource::Manual.as_str())
+        .bind(req.channel.as_str())
+        .bind(req.direction.as_str())
+        .bind(trimmed(&req.recipient))
+        .bind(trimmed(&req.subject))
+        .bind(notes)
+        .bind(occurred_at)
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        info!(
+            "Communication {} ({}) recorded for patient {} by {}",
+            entry.id, entry.channel, patient_id, user_id
+        );
+
+        self.decrypt(&entry, Vec::new())
+    }
+
+    /// Add an annotation to a log entry
+    pub async fn annotate(
+        &self,
+        patient_id: Uuid,
+        communication_id: Uuid,
+        req: &CreateCommunicationAnnotationRequest,
+        user_id: Uuid,
+    ) -> Result<CommunicationAnnotationResponse> {
+        let note = self
+            .encryption_key
+            .encrypt(req.note.trim())
+            .map_err(|e| AppError::Internal(format!("Failed to encrypt annotation: {}", e)))?;
+
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        self.find(&mut tx, patient_id, communication_id).await?;
+
+        let annotation = sqlx::query_as::<_, CommunicationAnnotation>(
+            r#"
+            INSERT INTO patient_communication_annotations (communication_id, note, created_by)
+            VALUES ($1, $2, $3)
+            RETURNING id, communication_id, note, created_by, created_at
+            "#,
+        )
+        .bind(communication_id)
+        .bind(note)
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        annotation
+            .decrypt(&self.encryption_key)
+            .map_err(|e| AppError::Internal(format!("Failed to decrypt annotation: {}", e)))
+    }
+
+    /// Log a sent notification
+    ///
+    /// Only notifications to a patient are logged: those to staff (with a
+    /// `user_id`, e.g. referral reminders) and those of erased or merged
+    /// patients are skipped. Logging the same notification twice is a no-op.
+    pub async fn record_notification(
+        pool: &PgPool,
+        notification_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<()> {
+        let mut tx = begin_with_rls(pool, user_id).await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO patient_communications (
+                patient_id, source, channel, notification_id, recipient, subject,
+                occurred_at, created_by
+            )
+            SELECT nq.patient_id, $2,
+                   CASE WHEN nq.delivery_method IN ('EMAIL', 'SMS', 'WHATSAPP')
+                        THEN nq.delivery_method ELSE 'OTHER' END,
+                   nq.id,
+                   COALESCE(nq.recipient_email, nq.recipient_phone),
+                   LEFT(nq.subject, 255), COALESCE(nq.sent_at, NOW()), $3
+            FROM notification_queue nq
+            JOIN patients p ON p.id = nq.patient_id
+            WHERE nq.id = $1
+              AND nq.user_id IS NULL
+              AND p.anonymized_at IS NULL
+              AND p.merged_at IS NULL
+            ON CONFLICT (notification_id) WHERE notification_id IS NOT NULL DO NOTHING
+            "#,
+        )
+        .bind(notification_id)
+        .bind(CommunicationSource::Notification.as_str())
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Log a generated document marked delivered
+    ///
+    /// `delivery_method` is the free-text method given on delivery ("email",
+    /// "print"...), mapped to a channel.
+    pub async fn record_document(
+        pool: &PgPool,
+        document_id: Uuid,
+        delivery_method: Option<&str>,
+        user_id: Uuid,
+    ) -> Result<()> {
+        let channel = delivery_channel(delivery_method);
+        let mut tx = begin_with_rls(pool, user_id).await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO patient_communications (
+                patient_id, source, channel, document_id, recipient, subject,
+                occurred_at, created_by
+            )
+            SELECT d.patient_id, $2, $3, d.id, d.delivered_to, LEFT(d.document_title, 255),
+                   COALESCE(d.delivered_at, NOW()), $4
+            FROM generated_documents d
+            JOIN patients p ON p.id = d.patient_id
+            WHERE d.id = $1
+              AND p.anonymized_at IS NULL
+              AND p.merged_at IS NULL
+            "#,
+        )
+        .bind(document_id)
+        .bind(CommunicationSource::Document.as_str())
+        .bind(channel.as_str())
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn find(
+        &self,
+        tx: &mut PgConnection,
+        patient_id: Uuid,
+        communication_id: Uuid,
+    ) -> Result<PatientCommunication> {
+        sqlx::query_as::<_, PatientCommunication>(&format!(
+            "SELECT {} FROM patient_communications WHERE id = $1 AND patient_id = $2",
+            COMMUNICATION_COLUMNS
+        ))
+        .bind(communication_id)
+        .bind(patient_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Communication {} not found", communication_id)))
+    }
+
+    /// The patient must exist and be neither anonymized nor merged
+    async fn check_patient(&self, tx: &mut PgConnection, patient_id: Uuid) -> Result<()> {
+        let patient: Option<(
+            Option<chrono::DateTime<chrono::Utc>>,
+            Option<chrono::DateTime<chrono::Utc>>,
+        )> = sqlx::query_as("SELECT anonymized_at, merged_at FROM patients WHERE id = $1")
+            .bind(patient_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        match patient {
+            None => Err(AppError::NotFound(format!(
+                "Patient {} not found",
+                patient_id
+            ))),
+            Some((Some(_), _)) => Err(AppError::Conflict(format!(
+                "Patient {} has been anonymized",
+                patient_id
+            ))),
+            Some((_, Some(_))) => Err(AppError::Conflict(format!(
+                "Patient {} has been merged into another record",
+                patient_id
+            ))),
+            Some(_) => Ok(()),
+        }
+    }
+
+    async fn annotations(
+        &self,
+        tx: &mut PgConnection,
+        communication_ids: &[Uuid],
+    ) -> Result<Vec<CommunicationAnnotationResponse>> {
+        let rows = sqlx::query_as::<_, CommunicationAnnotation>(
+            r#"
+            SELECT id, communication_id, note, created_by, created_at
+            FROM patient_communication_annotations
+            WHERE communication_id = ANY($1)
+            ORDER BY created_at
+            "#,
+        )
+        .bind(communication_ids)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                row.decrypt(&self.encryption_key)
+                    .map_err(|e| AppError::Internal(format!("Failed to decrypt annotation: {}", e)))
+            })
+            .collect()
+    }
+
+    fn decrypt(
+        &self,
+        entry: &PatientCommunication,
+        annotations: Vec<CommunicationAnnotationResponse>,
+    ) -> Result<PatientCommunicationResponse> {
+        entry
+            .decrypt(&self.encryption_key, annotations)
+            .map_err(|e| AppError::Internal(format!("Failed to decrypt communication: {}", e)))
+    }
+}
+
+/// Channel of a document delivery method ("email", "print", "hand"...)
+fn delivery_channel(delivery_method: Option<&str>) -> CommunicationChannel {
+    let method = delivery_method.unwrap_or_default().trim().to_lowercase();
+    match method.as_str() {
+        "email" | "e-mail" | "pec" => CommunicationChannel::Email,
+        "print" | "letter" | "mail" | "post" => CommunicationChannel::Letter,
+        "hand" | "in_person" | "in person" | "desk" => CommunicationChannel::InPerson,
+        _ => CommunicationChannel::Other,
+    }
+}
+
+/// Trimmed value, None when blank
+fn trimmed(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|v| !v.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delivery_channel() {
+        assert_eq!(delivery_channel(Some("email")), CommunicationChannel::Email);
+        assert_eq!(
+            delivery_channel(Some(" Print ")),
+            CommunicationChannel::Letter
+        );
+        assert_eq!(
+            delivery_channel(Some("hand")),
+            CommunicationChannel::InPerson
+        );
+        assert_eq!(delivery_channel(Some("fax")), CommunicationChannel::Other);
+        assert_eq!(delivery_channel(None), CommunicationChannel::Other);
+    }
+
+    #[test]
+    fn test_trimmed() {
+        assert_eq!(trimmed(&Some("  Mario  ".to_string())), Some("Mario"));
+        assert_eq!(trimmed(&Some("   ".to_string())), None);
+        assert_eq!(trimmed(&None), None);
+    }
+}
@@ -0,0 +1,476 @@
+/*!
+ * Patient Consent Service
+ *
+ * Versioned consent texts and the consents patients give to them:
+ * - publishing a text creates the next version for its type; published
+ *   texts are never modified
+ * - recording a consent supersedes the patient's current consent of the same
+ *   type, so at most one is GRANTED at a time
+ * - withdrawing a consent ends it immediately
+ *
+ * Handwritten signatures are stored encrypted. Other modules check consents
+ * through `has_active_consent` (e.g. before sending marketing notifications).
+ */
+
+use crate::{
+    db::rls::begin_with_rls,
+    models::{
+        ConsentText, ConsentTextResponse, ConsentType, CreateConsentTextRequest,
+        ListConsentTextsQuery, ListPatientConsentsQuery, PatientConsent,
+        PatientConsentDetailResponse, PatientConsentResponse, RecordConsentRequest,
+    },
+    utils::{encryption::EncryptionKey, AppError, Result},
+};
+use chrono::Utc;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+const TEXT_COLUMNS: &str = r#"
+    id, consent_type, version, title, body, validity_days, created_by, created_at
+"#;
+
+/// Consent columns, selected from `pc` joined with its text `ct`
+const CONSENT_COLUMNS: &str = r#"
+    pc.id, pc.patient_id, pc.consent_type, pc.consent_text_id,
+    ct.version AS consent_text_version, pc.status, pc.granted_at, pc.expires_at,
+    pc.signature_method, pc.signature_data, pc.signer_name, pc.signer_relationship,
+    pc.withdrawn_at, pc.withdrawn_by, pc.withdrawal_reason, pc.recorded_by,
+    pc.created_at, pc.updated_at
+"#;
+
+/// Patient consent service
+pub struct PatientConsentService {
+    pool: PgPool,
+    encryption_key: EncryptionKey,
+}
+
+impl PatientConsentService {
+    /// Create a new patient consent service
+    pub fn new(pool: PgPool, encryption_key: EncryptionKey) -> Self {
+        Self {
+            pool,
+            encryption_key,
+        }
+    }
+
+    // ========================================================================
+    // CONSENT TEXTS
+    // ========================================================================
+
+    /// List consent texts, newest version first within each type
+    pub async fn list_texts(
+        &self,
+        query: &ListConsentTextsQuery,
+    ) -> Result<Vec<ConsentTextResponse>> {
+        let texts = sqlx::query_as::<_, ConsentText>(&format!(
+            r#"
+            SELECT {}
+            FROM consent_texts ct
+            WHERE ($1::TEXT IS NULL OR consent_type = $1)
+              AND (NOT $2 OR version = (
+                  SELECT MAX(version) FROM consent_texts latest
+                  WHERE latest.consent_type = ct.consent_type
+              ))
+            ORDER BY consent_type, version DESC
+            "#,
+            TEXT_COLUMNS
+        ))
+        .bind(query.consent_type.map(|t| t.as_str()))
+        .bind(query.latest_only)
+        .fetch_all(&self.pool)
+        .await?;
+
+        texts.into_iter().map(text_response).collect()
+    }
+
+    /// Get a consent text by ID
+    pub async fn get_text(&self, text_id: Uuid) -> Result<ConsentTextResponse> {
+        let text = self
+            .fetch_text(text_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Consent text {} not found", text_id)))?;
+
+        text_response(text)
+    }
+
+    /// Publish a new version of a consent text
+    pub async fn create_text(
+        &self,
+        req: &CreateConsentTextRequest,
+        created_by: Uuid,
+    ) -> Result<ConsentTextResponse> {
+        let result = sqlx::query_as::<_, ConsentText>(&format!(
+            r#"
+            INSERT INTO consent_texts (consent_type, version, title, body, validity_days, created_by)
+            SELECT $1, COALESCE(MAX(version), 0) + 1, $2, $3, $4, $5
+            FROM consent_texts
+            WHERE consent_type = $1
+            RETURNING {}
+            "#,
+            TEXT_COLUMNS
+        ))
+        .bind(req.consent_type.as_str())
+        .bind(&req.title)
+        .bind(&req.body)
+        .bind(req.validity_days)
+        .bind(created_by)
+        .fetch_one(&self.pool)
+        .await;
+
+        match result {
+            Ok(text) => text_response(text),
+            Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+                Err(AppError::Conflict(format!(
+                    "A new {} consent text was published concurrently, please retry",
+                    req.consent_type.as_str()
+                )))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn fetch_text(&self, text_id: Uuid) -> Result<Option<ConsentText>> {
+        Ok(sqlx::query_as::<_, ConsentText>(&format!(
+            "SELECT {} FROM consent_texts WHERE id = $1",
+            TEXT_COLUMNS
+        ))
+        .bind(text_id)
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    /// Latest published text of a consent type
+    async fn latest_text(&self, consent_type: ConsentType) -> Result<Option<ConsentText>> {
+        Ok(sqlx::query_as::<_, ConsentText>(&format!(
+            r#"
+            SELECT {}
+            FROM consent_texts
+            WHERE consent_type = $1
+            ORDER BY version DESC
+            LIMIT 1
+            "#,
+            TEXT_COLUMNS
+        ))
+        .bind(consent_type.as_str())
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    // ========================================================================
+    // PATIENT CONSENTS
+    // ========================================================================
+
+    /// List a patient's consents, newest first
+    pub async fn list_patient_consents(
+        &self,
+        patient_id: Uuid,
+        query: &ListPatientConsentsQuery,
+        user_id: Uuid,
+    ) -> Result<Vec<PatientConsentResponse>> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        ensure_patient_exists(&mut tx, patient_id).await?;
+
+        let consents = sqlx::query_as::<_, PatientConsent>(&format!(
+            r#"
+            SELECT {}
+            FROM patient_consents pc
+            JOIN consent_texts ct ON ct.id = pc.consent_text_id
+            WHERE pc.patient_id = $1
+              AND ($2::TEXT IS NULL OR pc.consent_type = $2)
+              AND (NOT $3 OR (
+                  pc.status = 'GRANTED' AND (pc.expires_at IS NULL OR pc.expires_at > NOW())
+              ))
+            ORDER BY pc.granted_at DESC
+            "#,
+            CONSENT_COLUMNS
+        ))
+        .bind(patient_id)
+        .bind(query.consent_type.map(|t| t.as_str()))
+        .bind(query.active_only)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        let now = Utc::now();
+        consents
+            .into_iter()
+            .map(|consent| consent_response(consent, now))
+            .collect()
+    }
+
+    /// Get a patient consent with its text and decrypted signature
+    pub async fn get_patient_consent(
+        &self,
+        patient_id: Uuid,
+        consent_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<PatientConsentDetailResponse> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        let consent = fetch_consent(&mut tx, patient_id, consent_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Consent {} not found", consent_id)))?;
+        tx.commit().await?;
+
+        let text = self
+            .fetch_text(consent.consent_text_id)
+            .await?
+            .ok_or_else(|| {
+                AppError::Internal(format!(
+                    "Consent text {} of consent {} is missing",
+                    consent.consent_text_id, consent_id
+                ))
+            })?;
+
+        let signature_data = self
+            .encryption_key
+            .decrypt_optional(&consent.signature_data)
+            .map_err(|e| AppError::Internal(format!("Failed to decrypt signature: {}", e)))?;
+
+        let mut response = consent_response(consent, Utc::now())?;
+        response.signature_data = signature_data;
+
+        Ok(PatientConsentDetailResponse {
+            consent: response,
+            consent_text: text_response(text)?,
+        })
+    }
+
+    /// Record a patient's consent, superseding the current one of the same type
+    pub async fn record_consent(
+        &self,
+        patient_id: Uuid,
+        req: &RecordConsentRequest,
+        recorded_by: Uuid,
+    ) -> Result<PatientConsentResponse> {
+        let text = match req.consent_text_id {
+            Some(text_id) => self.fetch_text(text_id).await?.ok_or_else(|| {
+                AppError::Validation(format!("Consent text {} not found", text_id))
+            })?,
+            None => self.latest_text(req.consent_type).await?.ok_or_else(|| {
+                AppError::Validation(format!(
+                    "No {} consent text has been published",
+                    req.consent_type.as_str()
+                ))
+            })?,
+        };
+
+        if text.consent_type != req.consent_type.as_str() {
+            return Err(AppError::Validation(format!(
+                "Consent text {} is a {} text, not {}",
+                text.id,
+                text.consent_type,
+                req.consent_type.as_str()
+            )));
+        }
+
+        let granted_at = Utc::now();
+        let expires_at = req.expires_at.or_else(|| text.expiry_from(granted_at));
+        if expires_at.is_some_and(|expires_at| expires_at <= granted_at) {
+            return Err(AppError::Validation(
+                "expires_at must be in the future".to_string(),
+            ));
+        }
+
+        let signature_data = self
+            .encryption_key
+            .encrypt_optional(&req.signature_data)
+            .map_err(|e| AppError::Internal(format!("Failed to encrypt signature: {}", e)))?;
+
+        let mut tx = begin_with_rls(&self.pool, recorded_by).await?;
+
+        let anonymized_at: Option<Option<chrono::DateTime<Utc>>> =
+            sqlx::query_scalar("SELECT anonymized_at FROM patients WHERE id = $1")
+                .bind(patient_id)
+                .fetch_optional(&mut *tx)
+                .await?;
+        match anonymized_at {
+            None => {
+                return Err(AppError::NotFound(format!(
+                    "Patient {} not found",
+                    patient_id
+                )))
+            }
+            Some(Some(_)) => {
+                return Err(AppError::Conflict(format!(
+                    "Patient {} has been anonymized",
+                    patient_id
+                )))
+            }
+            Some(None) => {}
+        }
+
+        sqlx::query(
+            r#"
+            UPDATE patient_consents
+            SET status = 'SUPERSEDED'
+            WHERE patient_id = $1 AND consent_type = $2 AND status = 'GRANTED'
+            "#,
+        )
+        .bind(patient_id)
+        .bind(req.consent_type.as_str())
+        .execute(&mut *tx)
+        .await?;
+
+        let consent = sqlx::query_as::<_, PatientConsent>(&format!(
+            r#"
+            WITH pc AS (
+                INSERT INTO patient_consents (
+                    patient_id, consent_type, consent_text_id, granted_at, expires_at,
+                    signature_method, signature_data, signer_name, signer_relationship,
+                    recorded_by
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                RETURNING *
+            )
+            SELECT {}
+            FROM pc
+            JOIN consent_texts ct ON ct.id = pc.consent_text_id
+            "#,
+            CONSENT_COLUMNS
+        ))
+        .bind(patient_id)
+        .bind(req.consent_type.as_str())
+        .bind(text.id)
+        .bind(granted_at)
+        .bind(expires_at)
+        .bind(req.signature_method.as_str())
+        .bind(signature_data)
+        .bind(&req.signer_name)
+        .bind(&req.signer_relationship)
+        .bind(recorded_by)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        consent_response(consent, granted_at)
+    }
+
+    /// Withdraw a granted consent
+    ///
+    /// Fails with Conflict if the consent is already withdrawn or superseded.
+    pub async fn withdraw_consent(
+        &self,
+        patient_id: Uuid,
+        consent_id: Uuid,
+        reason: Option<&str>,
+        withdrawn_by: Uuid,
+    ) -> Result<PatientConsentResponse> {
+        let mut tx = begin_with_rls(&self.pool, withdrawn_by).await?;
+
+        let existing = fetch_consent(&mut tx, patient_id, consent_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Consent {} not found", consent_id)))?;
+
+        if existing.status != "GRANTED" {
+            return Err(AppError::Conflict(format!(
+                "Consent {} is {} and cannot be withdrawn",
+                consent_id, existing.status
+            )));
+        }
+
+        let consent = sqlx::query_as::<_, PatientConsent>(&format!(
+            r#"
+            WITH pc AS (
+                UPDATE patient_consents
+                SET status = 'WITHDRAWN', withdrawn_at = NOW(), withdrawn_by = $2,
+                    withdrawal_reason = $3
+                WHERE id = $1 AND status = 'GRANTED'
+                RETURNING *
+            )
+            SELECT {}
+            FROM pc
+            JOIN consent_texts ct ON ct.id = pc.consent_text_id
+            "#,
+            CONSENT_COLUMNS
+        ))
+        .bind(consent_id)
+        .bind(withdrawn_by)
+        .bind(reason)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| {
+            AppError::Conflict(format!("Consent {} was changed concurrently", consent_id))
+        })?;
+
+        tx.commit().await?;
+
+        consent_response(consent, Utc::now())
+    }
+
+    /// Whether the patient currently has an active consent of this type
+    pub async fn has_active_consent(
+        pool: &PgPool,
+        patient_id: Uuid,
+        consent_type: ConsentType,
+        user_id: Uuid,
+    ) -> Result<bool> {
+        let mut tx = begin_with_rls(pool, user_id).await?;
+
+        let active: bool = sqlx::query_scalar(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM patient_consents
+                WHERE patient_id = $1
+                  AND consent_type = $2
+                  AND status = 'GRANTED'
+                  AND (expires_at IS NULL OR expires_at > NOW())
+            )
+            "#,
+        )
+        .bind(patient_id)
+        .bind(consent_type.as_str())
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(active)
+    }
+}
+
+async fn ensure_patient_exists(tx: &mut PgConnection, patient_id: Uuid) -> Result<()> {
+    let exists: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM patients WHERE id = $1)")
+        .bind(patient_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    if !exists {
+        return Err(AppError::NotFound(format!(
+            "Patient {} not found",
+            patient_id
+        )));
+    }
+    Ok(())
+}
+
+async fn fetch_consent(
+    tx: &mut PgConnection,
+    patient_id: Uuid,
+    consent_id: Uuid,
+) -> Result<Option<PatientConsent>> {
+    Ok(sqlx::query_as::<_, PatientConsent>(&format!(
+        r#"
+        SELECT {}
+        FROM patient_consents pc
+        JOIN consent_texts ct ON ct.id = pc.consent_text_id
+        WHERE pc.id = $1 AND pc.patient_id = $2
+        "#,
+        CONSENT_COLUMNS
+    ))
+    .bind(consent_id)
+    .bind(patient_id)
+    .fetch_optional(&mut *tx)
+    .await?)
+}
+
+fn text_response(text: ConsentText) -> Result<ConsentTextResponse> {
+    ConsentTextResponse::try_from(text).map_err(AppError::Internal)
+}
+
+fn consent_response(
+    consent: PatientConsent,
+    now: chrono::DateTime<Utc>,
+) -> Result<PatientConsentResponse> {
+    PatientConsentResponse::from_consent(consent, now).map_err(AppError::Internal)
+}
@@ -0,0 +1,594 @@
+/*!
+ * Patient Erasure Service
+ *
+ * GDPR right to erasure. Requests are filed by one admin and approved or
+ * rejected by another (four-eyes). Approval anonymizes the patient in a
+ * single transaction:
+ * - identifying fields on the patient row are replaced or cleared and the
+ *   row is tombstoned (`anonymized_at`); a trigger makes it read-only
+ * - insurance and notification preferences are deleted
+ * - unsent notifications are cancelled and every notification's recipient
+ *   and content are scrubbed
+ * - communication log entries lose their recipient, subject and notes, and
+ *   their annotations are deleted
+ * - message threads are closed and their subjects and messages scrubbed
+ * - future appointments are cancelled
+ * - generated documents are marked deleted and their generation data cleared
+ * - subject access export bundles are expired
+ * - the patient photo and its thumbnail are deleted
+ *
+ * Document PDFs, export bundles and photos are removed from storage after
+ * the transaction commits. Visits, diagnoses, prescriptions and audit logs are
+ * retained as required for medical records.
+ */
+
+use crate::{
+    db::rls::begin_with_rls,
+    models::{
+        patient_erasure::{
+            anonymized_date_of_birth, ANONYMIZED_FIRST_NAME, ANONYMIZED_LAST_NAME,
+            ERASED_MESSAGE_BODY,
+        },
+        ApproveErasureRequest, ErasureRequestStatus, ErasureSummary, ListErasureRequestsQuery,
+        PatientErasureRequest, PatientErasureRequestResponse, PatientExportStatus,
+    },
+    services::FileUploadService,
+    utils::{encryption::EncryptionKey, AppError, Result},
+};
+use sqlx::{PgConnection, PgPool};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Email written over erased EMAIL notifications (valid_recipient requires one)
+const ERASED_RECIPIENT_EMAIL: &str = "erased@invalid";
+
+/// Phone written over erased SMS/WhatsApp notifications
+const ERASED_RECIPIENT_PHONE: &str = "+000000000";
+
+/// Reason recorded on appointments cancelled by an erasure
+const ERASURE_CANCELLATION_REASON: &str = "Patient data erased (GDPR)";
+
+const REQUEST_COLUMNS: &str = r#"
+    id, patient_id, reason, status, requested_by, requested_at, reviewed_by,
+    reviewed_at, review_notes, completed_at, summary
+"#;
+
+/// Patient row fields needed to approve an erasure
+#[derive(Debug, sqlx::FromRow)]
+struct ErasurePatientRow {
+    medical_record_number: String,
+    date_of_birth: String,
+    status: String,
+    anonymized_at: Option<chrono::DateTime<chrono::Utc>>,
+    photo_file_id: Option<Uuid>,
+    photo_thumbnail_file_id: Option<Uuid>,
+}
+
+/// Patient erasure service
+pub struct PatientErasureService {
+    pool: PgPool,
+    encryption_key: EncryptionKey,
+}
+
+impl PatientErasureService {
+    /// Create a new patient erasure service
+    pub fn new(pool: PgPool, encryption_key: EncryptionKey) -> Self {
+        Self {
+            pool,
+            encryption_key,
+        }
+    }
+
+    /// File an erasure request for a patient
+    ///
+    /// Fails with Conflict if the patient is already anonymized or already
+    /// has a pending request.
+    pub async fn create_request(
+        &self,
+        patient_id: Uuid,
+        reason: &str,
+        requested_by: Uuid,
+    ) -> Result<PatientErasureRequestResponse> {
+        let mut tx = begin_with_rls(&self.pool, requested_by).await?;
+
+        let anonymized_at: Option<Option<chrono::DateTime<chrono::Utc>>> =
+            sqlx::query_scalar("SELECT anonymized_at FROM patients WHERE id = $1")
+                .bind(patient_id)
+                .fetch_optional(&mut *tx)
+                .await?;
+
+        match anonymized_at {
+            None => {
+                return Err(AppError::NotFound(format!(
+                    "Patient {} not found",
+                    patient_id
+                )))
+            }
+            Some(Some(_)) => {
+                return Err(AppError::Conflict(format!(
+                    "Patient {} has already been anonymized",
+                    patient_id
+                )))
+            }
+            Some(None) => {}
+        }
+
+        let request = sqlx::query_as::<_, PatientErasureRequest>(&format!(
+            r#"
+            INSERT INTO patient_erasure_requests (patient_id, reason, requested_by)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (patient_id) WHERE status = 'PENDING' DO NOTHING
+            RETURNING {}
+            "#,
+            REQUEST_COLUMNS
+        ))
+        .bind(patient_id)
+        .bind(reason)
+        .bind(requested_by)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| {
+            AppError::Conflict(format!(
+                "Patient {} already has a pending erasure request",
+                patient_id
+            ))
+        })?;
+
+        tx.commit().await?;
+
+        Ok(PatientErasureRequestResponse::from(request))
+    }
+
+    /// List erasure requests, newest first
+    pub async fn list_requests(
+        &self,
+        query: &ListErasureRequestsQuery,
+    ) -> Result<Vec<PatientErasureRequestResponse>> {
+        let requests = sqlx::query_as::<_, PatientErasureRequest>(&format!(
+            r#"
+            SELECT {}
+            FROM patient_erasure_requests
+            WHERE ($1::TEXT IS NULL OR status = $1)
+              AND ($2::UUID IS NULL OR patient_id = $2)
+            ORDER BY requested_at DESC
+            "#,
+            REQUEST_COLUMNS
+        ))
+        .bind(query.status.map(|status| status.as_str()))
+        .bind(query.patient_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(requests
+            .into_iter()
+            .map(PatientErasureRequestResponse::from)
+            .collect())
+    }
+
+    /// Get an erasure request by ID
+    pub async fn get_request(&self, request_id: Uuid) -> Result<PatientErasureRequestResponse> {
+        let request = sqlx::query_as::<_, PatientErasureRequest>(&format!(
+            "SELECT {} FROM patient_erasure_requests WHERE id = $1",
+            REQUEST_COLUMNS
+        ))
+        .bind(request_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Erasure request {} not found", request_id)))?;
+
+        Ok(PatientErasureRequestResponse::from(request))
+    }
+
+    /// Reject a pending erasure request
+    pub async fn reject_request(
+        &self,
+        request_id: Uuid,
+        notes: &str,
+        reviewed_by: Uuid,
+    ) -> Result<PatientErasureRequestResponse> {
+        let mut tx = begin_with_rls(&self.pool, reviewed_by).await?;
+
+        let request = self
+            .lock_pending_request(&mut tx, request_id, reviewed_by)
+            .await?;
+
+        let rejected = sqlx::query_as::<_, PatientErasureRequest>(&format!(
+            r#"
+            UPDATE patient_erasure_requests
+            SET status = $2, reviewed_by = $3, reviewed_at = NOW(), review_notes = $4
+            WHERE id = $1
+            RETURNING {}
+            "#,
+            REQUEST_COLUMNS
+        ))
+        .bind(request.id)
+        .bind(ErasureRequestStatus::Rejected.as_str())
+        .bind(reviewed_by)
+        .bind(notes)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(PatientErasureRequestResponse::from(rejected))
+    }
+
+    /// Approve a pending erasure request and anonymize the patient
+    ///
+    /// Irreversible. The reviewer must differ from the requester and must
+    /// confirm the patient's medical record number.
+    pub async fn approve_request(
+        &self,
+        request_id: Uuid,
+        approval: &ApproveErasureRequest,
+        reviewed_by: Uuid,
+    ) -> Result<PatientErasureRequestResponse> {
+        let mut tx = begin_with_rls(&self.pool, reviewed_by).await?;
+
+        let request = self
+            .lock_pending_request(&mut tx, request_id, reviewed_by)
+            .await?;
+        let patient_id = request.patient_id;
+
+        let patient = sqlx::query_as::<_, ErasurePatientRow>(
+            r#"
+            SELECT medical_record_number, date_of_birth, status, anonymized_at,
+                   photo_file_id, photo_thumbnail_file_id
+            FROM patients
+            WHERE id = $1
+            FOR UPDATE
+            "#,
+        )
+        .bind(patient_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Patient {} not found", patient_id)))?;
+
+        if patient.anonymized_at.is_some() {
+            return Err(AppError::Conflict(format!(
+                "Patient {} has already been anonymized",
+                patient_id
+            )));
+        }
+
+        if patient.medical_record_number != approval.confirm_medical_record_number.trim() {
+            return Err(AppError::Validation(
+                "Medical record number does not match the patient of this request".to_string(),
+            ));
+        }
+
+        // Scopes the erasure RLS policies to this patient for this transaction
+        sqlx::query("SELECT set_config('app.erasure_patient_id', $1, true)")
+            .bind(patient_id.to_string())
+            .execute(&mut *tx)
+            .await?;
+
+        let mut summary = ErasureSummary::default();
+
+        summary.insurance_deleted =
+            sqlx::query("DELETE FROM patient_insurance WHERE patient_id = $1")
+                .bind(patient_id)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+
+        summary.notification_preferences_deleted =
+            sqlx::query("DELETE FROM patient_notification_preferences WHERE patient_id = $1")
+                .bind(patient_id)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+
+        summary.notifications_cancelled = sqlx::query(
+            r#"
+            UPDATE notification_queue
+            SET status = 'CANCELLED'
+            WHERE patient_id = $1 AND status IN ('PENDING', 'FAILED')
+            "#,
+        )
+        .bind(patient_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        summary.notifications_scrubbed = sqlx::query(
+            r#"
+            UPDATE notification_queue
+            SET recipient_email = CASE WHEN delivery_method = 'EMAIL' THEN $2 ELSE NULL END,
+                recipient_phone = CASE WHEN delivery_method IN ('SMS', 'WHATSAPP') THEN $3 ELSE NULL END,
+                recipient_name = NULL,
+                subject = NULL,
+                message_body = $4,
+                metadata = NULL
+            WHERE patient_id = $1
+            "#,
+        )
+        .bind(patient_id)
+        .bind(ERASED_RECIPIENT_EMAIL)
+        .bind(ERASED_RECIPIENT_PHONE)
+        .bind(ERASED_MESSAGE_BODY)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        // The log keeps when and how the patient was contacted, not what was
+        // said or where to
+        sqlx::query(
+            r#"
+            DELETE FROM patient_communication_annotations
+            WHERE communication_id IN (
+                SELECT id FROM patient_communications WHERE patient_id = $1
+            )
+            "#,
+        )
+        .bind(patient_id)
+        .execute(&mut *tx)
+        .await?;
+
+        summary.communications_scrubbed = sqlx::query(
+            r#"
+            UPDATE patient_communications
+            SET recipient = NULL, subject = NULL, notes = NULL
+            WHERE patient_id = $1
+            "#,
+        )
+        .bind(patient_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        // Subjects and bodies are encrypted, so the placeholder is too
+        let erased_message = self.encrypt(ERASED_MESSAGE_BODY)?;
+        sqlx::query(
+            r#"
+            UPDATE patient_message_threads
+            SET subject = $2,
+                status = 'CLOSED',
+                closed_at = COALESCE(closed_at, NOW()),
+                closed_by = COALESCE(closed_by, $3),
+                updated_at = NOW()
+            WHERE patient_id = $1
+            "#,
+        )
+        .bind(patient_id)
+        .bind(&erased_message)
+        .bind(reviewed_by)
+        .execute(&mut *tx)
+        .await?;
+
+        summary.messages_scrubbed = sqlx::query(
+            r#"
+            UPDATE patient_messages
+            SET body = $2
+            WHERE thread_id IN (
+                SELECT id FROM patient_message_threads WHERE patient_id = $1
+            )
+            "#,
+        )
+        .bind(patient_id)
+        .bind(&erased_message)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        summary.survey_comments_scrubbed = sqlx::query(
+            "UPDATE visit_surveys SET comment = NULL WHERE patient_id = $1 AND comment IS NOT NULL",
+        )
+        .bind(patient_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        summary.appointments_cancelled = sqlx::query(
+            r#"
+            UPDATE appointments
+            SET status = 'CANCELLED',
+                cancellation_reason = CASE WHEN text_encrypted THEN $4 ELSE $2 END,
+                cancelled_by = $3
+            WHERE patient_id = $1
+              AND status IN ('PENDING', 'SCHEDULED', 'CONFIRMED')
+              AND scheduled_start > NOW()
+            "#,
+        )
+        .bind(patient_id)
+        .bind(ERASURE_CANCELLATION_REASON)
+        .bind(reviewed_by)
+        .bind(self.encrypt(ERASURE_CANCELLATION_REASON)?)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        // Documents still on file move to DELETED; FAILED and GENERATING ones
+        // only lose their generation data
+        let document_files: Vec<String> = sqlx::query_scalar(
+            r#"
+            UPDATE generated_documents
+            SET status = CASE WHEN status IN ('GENERATED', 'DELIVERED') THEN 'DELETED' ELSE status END,
+                deleted_at = COALESCE(deleted_at, NOW()),
+                generation_data = NULL,
+                generation_request = NULL
+            WHERE patient_id = $1
+            RETURNING file_path
+            "#,
+        )
+        .bind(patient_id)
+        .fetch_all(&mut *tx)
+        .await?;
+        summary.documents_deleted = document_files.len() as u64;
+
+        let export_files: Vec<Option<String>> = sqlx::query_scalar(
+            r#"
+            WITH purged AS (
+                SELECT id, file_path FROM patient_data_exports
+                WHERE patient_id = $1 AND status <> $2
+                FOR UPDATE
+            )
+            UPDATE patient_data_exports e
+            SET status = $2, file_path = NULL
+            FROM purged
+            WHERE e.id = purged.id
+            RETURNING purged.file_path
+            "#,
+        )
+        .bind(patient_id)
+        .bind(PatientExportStatus::Expired.as_str())
+        .fetch_all(&mut *tx)
+        .await?;
+        summary.exports_purged = export_files.len() as u64;
+
+        // The patient row goes last: once anonymized_at is set it is read-only
+        let date_of_birth = self
+            .encryption_key
+            .decrypt(&patient.date_of_birth)
+            .ok()
+            .and_then(|dob| anonymized_date_of_birth(&dob))
+            .unwrap_or_else(|| "1900-01-01".to_string());
+        let status = if patient.status == "DECEASED" {
+            "DECEASED"
+        } else {
+            "INACTIVE"
+        };
+
+        sqlx::query(
+            r#"
+            UPDATE patients
+            SET first_name = $2,
+                last_name = $3,
+                middle_name = NULL,
+                date_of_birth = $4,
+                fiscal_code = NULL,
+                phone_primary = NULL,
+                phone_secondary = NULL,
+                email = NULL,
+                address = NULL,
+                emergency_contact = NULL,
+                health_card_expire = NULL,
+                photo_url = NULL,
+                photo_file_id = NULL,
+                photo_thumbnail_file_id = NULL,
+                notes = NULL,
+                fiscal_code_bidx = NULL,
+                last_name_bidx = NULL,
+                last_name_prefix_bidx = NULL,
+                phone_bidx = NULL,
+                email_bidx = NULL,
+                search_trigram_bidx = NULL,
+                status = $5,
+                anonymized_at = NOW(),
+                anonymized_by = $6,
+                updated_at = NOW(),
+                updated_by = $6
+            WHERE id = $1
+            "#,
+        )
+        .bind(patient_id)
+        .bind(self.encrypt(ANONYMIZED_FIRST_NAME)?)
+        .bind(self.encrypt(ANONYMIZED_LAST_NAME)?)
+        .bind(self.encrypt(&date_of_birth)?)
+        .bind(status)
+        .bind(reviewed_by)
+        .execute(&mut *tx)
+        .await?;
+
+        // Photo records go once the patient no longer references them
+        let photo_ids: Vec<Uuid> = [patient.photo_file_id, patient.photo_thumbnail_file_id]
+            .into_iter()
+            .flatten()
+            .collect();
+        let photo_files: Vec<String> = sqlx::query_scalar(
+            "DELETE FROM uploaded_files WHERE id = ANY($1) RETURNING storage_path",
+        )
+        .bind(&photo_ids)
+        .fetch_all(&mut *tx)
+        .await?;
+        summary.photos_deleted = photo_files.len() as u64;
+
+        let completed = sqlx::query_as::<_, PatientErasureRequest>(&format!(
+            r#"
+            UPDATE patient_erasure_requests
+            SET status = $2,
+                reviewed_by = $3,
+                reviewed_at = NOW(),
+                review_notes = $4,
+                completed_at = NOW(),
+                summary = $5
+            WHERE id = $1
+            RETURNING {}
+            "#,
+            REQUEST_COLUMNS
+        ))
+        .bind(request.id)
+        .bind(ErasureRequestStatus::Completed.as_str())
+        .bind(reviewed_by)
+        .bind(&approval.notes)
+        .bind(serde_json::to_value(&summary).unwrap_or_default())
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        info!(
+            "Patient {} anonymized (erasure request {})",
+            patient_id, request.id
+        );
+
+        for path in document_files.iter().chain(export_files.iter().flatten()) {
+            match tokio::fs::remove_file(path).await {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => warn!(
+                    "Failed to remove file of erased patient {}: {}: {}",
+                    patient_id, path, e
+                ),
+            }
+        }
+        for path in &photo_files {
+            if let Err(e) = FileUploadService::delete_file(path).await {
+                warn!(
+                    "Failed to remove photo of erased patient {}: {}: {}",
+                    patient_id, path, e
+                );
+            }
+        }
+
+        Ok(PatientErasureRequestResponse::from(completed))
+    }
+
+    /// Lock a PENDING request for review, enforcing four-eyes
+    async fn lock_pending_request(
+        &self,
+        tx: &mut PgConnection,
+        request_id: Uuid,
+        reviewed_by: Uuid,
+    ) -> Result<PatientErasureRequest> {
+        let request = sqlx::query_as::<_, PatientErasureRequest>(&format!(
+            "SELECT {} FROM patient_erasure_requests WHERE id = $1 FOR UPDATE",
+            REQUEST_COLUMNS
+        ))
+        .bind(request_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Erasure request {} not found", request_id)))?;
+
+        if request.status != ErasureRequestStatus::Pending.as_str() {
+            return Err(AppError::Conflict(format!(
+                "Erasure request {} has already been reviewed ({})",
+                request_id, request.status
+            )));
+        }
+
+        if request.requested_by == reviewed_by {
+            return Err(AppError::Forbidden(
+                "An erasure request must be reviewed by a different administrator".to_string(),
+            ));
+        }
+
+        Ok(request)
+    }
+
+    fn encrypt(&self, plaintext: &str) -> Result<String> {
+        self.encryption_key
+            .encrypt(plaintext)
+            .map_err(|e| AppError::Internal(format!("Failed to encrypt patient field: {}", e)))
+    }
+}
@@ -0,0 +1,842 @@
+/*!
+ * Patient Data Export Service
+ *
+ * Builds GDPR subject access bundles: every record stored about a patient,
+ * as JSON files plus the generated PDFs, in an AES-256 encrypted zip.
+ *
+ * Bundle layout:
+ * - manifest.json: format version, patient, generation time, record counts
+ * - patient.json: demographics, insurance and notification preferences
+ * - visits.json, diagnoses.json, prescriptions.json, appointments.json
+ * - documents.json plus one PDF per document under documents/
+ * - visit_attachments.json plus the photos, audio memos and files of the
+ *   visits under visit_attachments/
+ * - notifications.json: messages queued or sent to the patient
+ * - access_log.json: who accessed the record and when (from audit_logs)
+ *
+ * Records are read with the requesting admin's RLS context, through the
+ * same services as the API, so clinical fields are decrypted exactly as
+ * they would be on screen. Bundles are written under
+ * `<DOCUMENT_STORAGE_PATH>/exports` and deleted once they expire.
+ */
+
+use crate::{
+    db::rls::{begin_detached_with_rls, begin_with_rls},
+    models::{
+        audit_log::{PatientAccessEntry, PatientAccessLogFilter},
+        patient_export::PATIENT_EXPORT_FORMAT_VERSION,
+        patient_insurance::PatientInsurance,
+        AppointmentSearchFilter, PatientExport, PatientExportResponse, PatientExportStatus,
+    },
+    services::{
+        AppointmentService, AuditLogService, PatientService, PrescriptionService,
+        VisitAttachmentService, VisitDiagnosisService, VisitService,
+    },
+    utils::encryption::EncryptionKey,
+};
+use anyhow::{Context, Result};
+use chrono::{Duration, Utc};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tokio::time::{sleep, Duration as TokioDuration};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+use zip::{write::SimpleFileOptions, AesMode, CompressionMethod, ZipWriter};
+
+/// Page size used when walking paginated services
+const PAGE_SIZE: i64 = 100;
+
+/// Exports still PROCESSING after this many hours were interrupted (e.g. by
+/// a restart) and are failed by the cleanup task
+const STALE_PROCESSING_HOURS: i64 = 6;
+
+/// Minutes between cleanup runs
+const CLEANUP_INTERVAL_MINUTES: u64 = 60;
+
+const EXPORT_COLUMNS: &str = r#"
+    id, patient_id, requested_by, status, file_path, file_size_bytes, file_hash,
+    error_message, expires_at, download_count, last_downloaded_at, created_at, completed_at
+"#;
+
+/// File written into the bundle
+#[derive(Debug)]
+struct BundleEntry {
+    name: String,
+    data: Vec<u8>,
+}
+
+impl BundleEntry {
+    fn json(name: &str, value: &serde_json::Value) -> Result<Self> {
+        Ok(Self {
+            name: name.to_string(),
+            data: serde_json::to_vec_pretty(value).context("Failed to serialize bundle file")?,
+        })
+    }
+}
+
+/// Generated document to include in the bundle
+#[derive(Debug, sqlx::FromRow)]
+struct ExportDocumentRow {
+    id: Uuid,
+    visit_id: Option<Uuid>,
+    document_type: String,
+    document_title: String,
+    document_filename: String,
+    file_path: String,
+    status: String,
+    is_signed: Option<bool>,
+    signed_at: Option<chrono::DateTime<Utc>>,
+    created_at: chrono::DateTime<Utc>,
+}
+
+/// Patient data export service
+pub struct PatientExportService {
+    pool: PgPool,
+    encryption_key: EncryptionKey,
+    storage_path: PathBuf,
+}
+
+impl PatientExportService {
+    /// Create a new service writing bundles under `<storage_path>/exports`
+    pub fn new(pool: PgPool, encryption_key: EncryptionKey, storage_path: PathBuf) -> Self {
+        Self {
+            pool,
+            encryption_key,
+            storage_path: storage_path.join("exports"),
+        }
+    }
+
+    /// Record a new export in PROCESSING state
+    ///
+    /// `complete_export` builds the bundle (normally from a background task).
+    /// Committed on its own, so the task sees the export before the request ends.
+    /// Returns None if the patient does not exist or is not visible to the user.
+    pub async fn create_export(
+        &self,
+        patient_id: Uuid,
+        requested_by: Uuid,
+    ) -> Result<Option<PatientExportResponse>> {
+        let mut tx = begin_detached_with_rls(&self.pool, requested_by)
+            .await
+            .context("Failed to begin transaction")?;
+
+        let patient_exists: bool =
+            sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM patients WHERE id = $1)")
+                .bind(patient_id)
+                .fetch_one(&mut *tx)
+                .await
+                .context("Failed to check patient")?;
+
+        if !patient_exists {
+            return Ok(None);
+        }
+
+        let export = sqlx::query_as::<_, PatientExport>(&format!(
+            r#"
+            INSERT INTO patient_data_exports (patient_id, requested_by, status)
+            VALUES ($1, $2, $3)
+            RETURNING {}
+            "#,
+            EXPORT_COLUMNS
+        ))
+        .bind(patient_id)
+        .bind(requested_by)
+        .bind(PatientExportStatus::Processing.as_str())
+        .fetch_one(&mut *tx)
+        .await
+        .context("Failed to create patient export")?;
+
+        tx.commit().await.context("Failed to commit transaction")?;
+
+        Ok(Some(PatientExportResponse::from(export)))
+    }
+
+    /// Build a queued export and move it to COMPLETED
+    ///
+    /// On error the export is moved to FAILED with the error message and any
+    /// partially written file is removed.
+    pub async fn complete_export(
+        &self,
+        export_id: Uuid,
+        patient_id: Uuid,
+        requested_by: Uuid,
+        passphrase: String,
+        retention_days: i64,
+    ) -> Result<()> {
+        let file_path = self.storage_path.join(format!("{}.zip", export_id));
+        let built = self
+            .build_bundle(export_id, patient_id, requested_by, passphrase, &file_path)
+            .await;
+
+        match built {
+            Ok((file_size_bytes, file_hash)) => {
+                let expires_at = Utc::now() + Duration::days(retention_days.max(1));
+
+                sqlx::query(
+                    r#"
+                    UPDATE patient_data_exports
+                    SET status = $2,
+                        file_path = $3,
+                        file_size_bytes = $4,
+                        file_hash = $5,
+                        expires_at = $6,
+                        completed_at = NOW()
+                    WHERE id = $1 AND status = 'PROCESSING'
+                    "#,
+                )
+                .bind(export_id)
+                .bind(PatientExportStatus::Completed.as_str())
+                .bind(file_path.to_string_lossy().to_string())
+                .bind(file_size_bytes)
+                .bind(&file_hash)
+                .bind(expires_at)
+                .execute(&self.pool)
+                .await
+                .context("Failed to update patient export")?;
+
+                info!(
+                    "Patient export {} completed ({} bytes)",
+                    export_id, file_size_bytes
+                );
+                Ok(())
+            }
+            Err(e) => {
+                let _ = tokio::fs::remove_file(&file_path).await;
+
+                sqlx::query(
+                    r#"
+                    UPDATE patient_data_exports
+                    SET status = $2, error_message = $3, completed_at = NOW()
+                    WHERE id = $1 AND status = 'PROCESSING'
+                    "#,
+                )
+                .bind(export_id)
+                .bind(PatientExportStatus::Failed.as_str())
+                .bind(format!("{:#}", e))
+                .execute(&self.pool)
+                .await
+                .context("Failed to mark patient export as failed")?;
+
+                Err(e)
+            }
+        }
+    }
+
+    /// Get an export of the given patient
+    pub async fn get_export(
+        &self,
+        patient_id: Uuid,
+        export_id: Uuid,
+    ) -> Result<Option<PatientExport>> {
+        sqlx::query_as::<_, PatientExport>(&format!(
+            "SELECT {} FROM patient_data_exports WHERE id = $1 AND patient_id = $2",
+            EXPORT_COLUMNS
+        ))
+        .bind(export_id)
+        .bind(patient_id)
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to fetch patient export")
+    }
+
+    /// List a patient's exports, newest first
+    pub async fn list_exports(&self, patient_id: Uuid) -> Result<Vec<PatientExportResponse>> {
+        let exports = sqlx::query_as::<_, PatientExport>(&format!(
+            r#"
+            SELECT {} FROM patient_data_exports
+            WHERE patient_id = $1
+            ORDER BY created_at DESC
+            LIMIT 50
+            "#,
+            EXPORT_COLUMNS
+        ))
+        .bind(patient_id)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to list patient exports")?;
+
+        Ok(exports
+            .into_iter()
+            .map(PatientExportResponse::from)
+            .collect())
+    }
+
+    /// Count a download of a completed export
+    pub async fn record_download(&self, export_id: Uuid) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE patient_data_exports
+            SET download_count = download_count + 1, last_downloaded_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(export_id)
+        .execute(&self.pool)
+        .await
+        .context("Failed to record patient export download")?;
+
+        Ok(())
+    }
+
+    /// Delete expired bundles from storage and fail interrupted exports
+    ///
+    /// Returns the number of bundles expired.
+    pub async fn purge_expired(&self) -> Result<usize> {
+        let interrupted = sqlx::query(
+            r#"
+            UPDATE patient_data_exports
+            SET status = 'FAILED',
+                error_message = 'Export interrupted before completion',
+                completed_at = NOW()
+            WHERE status = 'PROCESSING'
+              AND created_at < NOW() - make_interval(hours => $1)
+            "#,
+        )
+        .bind(STALE_PROCESSING_HOURS as i32)
+        .execute(&self.pool)
+        .await
+        .context("Failed to fail interrupted patient exports")?
+        .rows_affected();
+
+        if interrupted > 0 {
+            warn!("Failed {} interrupted patient exports", interrupted);
+        }
+
+        let expired: Vec<(Uuid, Option<String>)> = sqlx::query_as(
+            r#"
+            SELECT id, file_path FROM patient_data_exports
+            WHERE status = 'COMPLETED' AND expires_at <= NOW()
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to fetch expired patient exports")?;
+
+        for (id, file_path) in &expired {
+            if let Some(path) = file_path {
+                match tokio::fs::remove_file(path).await {
+                    Ok(()) => {}
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                    Err(e) => {
+                        // Keep the row COMPLETED so the next run retries
+                        warn!("Failed to delete expired patient export {}: {}", id, e);
+                        continue;
+                    }
+                }
+            }
+
+            sqlx::query(
+                r#"
+                UPDATE patient_data_exports
+                SET status = $2, file_path = NULL
+                WHERE id = $1
+                "#,
+            )
+            .bind(id)
+            .bind(PatientExportStatus::Expired.as_str())
+            .execute(&self.pool)
+            .await
+            .context("Failed to mark patient export as expired")?;
+        }
+
+        Ok(expired.len())
+    }
+
+    // ==================== Bundle Generation ====================
+
+    /// Collect the patient's records and write the encrypted zip
+    ///
+    /// Returns the zip size and SHA-256.
+    async fn build_bundle(
+        &self,
+        export_id: Uuid,
+        patient_id: Uuid,
+        requested_by: Uuid,
+        passphrase: String,
+        file_path: &Path,
+    ) -> Result<(i64, String)> {
+        let entries = self
+            .collect_entries(export_id, patient_id, requested_by)
+            .await?;
+
+        tokio::fs::create_dir_all(&self.storage_path)
+            .await
+            .context("Failed to create export directory")?;
+
+        let path = file_path.to_path_buf();
+        let bytes = tokio::task::spawn_blocking(move || {
+            write_encrypted_zip(&path, &entries, &passphrase)?;
+            std::fs::read(&path).context("Failed to read back patient export")
+        })
+        .await
+        .context("Patient export writer panicked")??;
+
+        let mut hasher = Sha256::new();
+        hasher.update(&bytes);
+
+        Ok((bytes.len() as i64, format!("{:x}", hasher.finalize())))
+    }
+
+    /// Load every section of the bundle
+    async fn collect_entries(
+        &self,
+        export_id: Uuid,
+        patient_id: Uuid,
+        requested_by: Uuid,
+    ) -> Result<Vec<BundleEntry>> {
+        let patient = PatientService::new(self.pool.clone(), self.encryption_key.clone())
+            .get_patient(patient_id, Some(requested_by), None)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Patient not found"))?;
+
+        let insurance = PatientInsurance::find_by_patient_id(&self.pool, patient_id)
+            .await?
+            .iter()
+            .map(|record| record.decrypt(&self.encryption_key))
+            .collect::<Result<Vec<_>>>()
+            .context("Failed to decrypt patient insurance")?;
+
+        let visit_service = VisitService::new(self.pool.clone(), self.encryption_key.clone());
+        let mut visits = Vec::new();
+        loop {
+            let page = visit_service
+                .get_patient_visits(
+                    patient_id,
+                    requested_by,
+                    Some(PAGE_SIZE),
+                    Some(visits.len() as i64),
+                )
+                .await?;
+            let done = (page.len() as i64) < PAGE_SIZE;
+            visits.extend(page);
+            if done {
+                break;
+            }
+        }
+
+        let diagnoses = VisitDiagnosisService::new(self.pool.clone(), self.encryption_key.clone())
+            .get_patient_diagnoses(patient_id, false)
+            .await?;
+
+        let role: String = sqlx::query_scalar("SELECT role::TEXT FROM users WHERE id = $1")
+            .bind(requested_by)
+            .fetch_one(&self.pool)
+            .await
+            .context("Failed to fetch user role")?;
+
+        let prescriptions =
+            PrescriptionService::new(self.pool.clone(), self.encryption_key.clone())
+                .get_patient_prescriptions(patient_id, false, requested_by, &role)
+                .await?;
+
+        let appointment_service = AppointmentService::new(self.pool.clone(), self.encryption_key.clone());
+        let mut appointments = Vec::new();
+        loop {
+            let filter = AppointmentSearchFilter {
+                patient_id: Some(patient_id),
+                provider_id: None,
+                status: None,
+                appointment_type: None,
+                start_date: None,
+                end_date: None,
+                limit: Some(PAGE_SIZE),
+                offset: Some(appointments.len() as i64),
+            };
+            let (page, total) = appointment_service
+                .list_appointments(filter, Some(requested_by), None)
+                .await?;
+            let fetched = page.len();
+            appointments.extend(page);
+            if fetched == 0 || appointments.len() as i64 >= total {
+                break;
+            }
+        }
+
+        let (notifications, preferences, documents) =
+            self.load_patient_rows(patient_id, requested_by).await?;
+
+        let access_log = self.load_access_log(patient_id, requested_by).await?;
+
+        let mut entries = Vec::new();
+        let mut document_files = Vec::new();
+        let mut documents_json = Vec::with_capacity(documents.len());
+
+        for (index, document) in documents.iter().enumerate() {
+            let mut bundle_file = None;
+            if matches!(document.status.as_str(), "GENERATED" | "DELIVERED") {
+                match tokio::fs::read(&document.file_path).await {
+                    Ok(data) => {
+                        let name = document_entry_name(index, &document.document_filename);
+                        bundle_file = Some(name.clone());
+                        document_files.push(BundleEntry { name, data });
+                    }
+                    Err(e) => warn!(
+                        "Patient export {}: document {} file unavailable: {}",
+                        export_id, document.id, e
+                    ),
+                }
+            }
+
+            documents_json.push(serde_json::json!({
+                "id": document.id,
+                "visit_id": document.visit_id,
+                "document_type": document.document_type,
+                "title": document.document_title,
+                "filename": document.document_filename,
+                "status": document.status,
+                "is_signed": document.is_signed,
+                "signed_at": document.signed_at,
+                "created_at": document.created_at,
+                "bundle_file": bundle_file,
+            }));
+        }
+
+        let attachment_service =
+            VisitAttachmentService::new(self.pool.clone(), self.encryption_key.clone());
+        let attachments = attachment_service
+            .list_patient_attachments(patient_id, requested_by)
+            .await?;
+        let mut attachment_files = Vec::new();
+        let mut attachments_json = Vec::with_capacity(attachments.len());
+
+        for (index, attachment) in attachments.iter().enumerate() {
+            let mut bundle_file = None;
+            match attachment_service
+                .download_attachment(attachment.visit_id, attachment.id, requested_by)
+                .await
+            {
+                Ok((_, data)) => {
+                    let name = attachment_entry_name(index, &attachment.original_filename);
+                    bundle_file = Some(name.clone());
+                    attachment_files.push(BundleEntry { name, data });
+                }
+                Err(e) => warn!(
+                    "Patient export {}: visit attachment {} file unavailable: {}",
+                    export_id, attachment.id, e
+                ),
+            }
+
+            let mut entry = serde_json::to_value(attachment)?;
+            if let serde_json::Value::Object(ref mut map) = entry {
+                map.remove("download_url");
+                map.insert("bundle_file".to_string(), serde_json::json!(bundle_file));
+            }
+            attachments_json.push(entry);
+        }
+
+        let manifest = serde_json::json!({
+            "format_version": PATIENT_EXPORT_FORMAT_VERSION,
+            "export_id": export_id,
+            "patient_id": patient_id,
+            "generated_at": Utc::now(),
+            "requested_by": requested_by,
+            "counts": {
+                "insurance": insurance.len(),
+                "visits": visits.len(),
+                "diagnoses": diagnoses.len(),
+                "prescriptions": prescriptions.len(),
+                "appointments": appointments.len(),
+                "documents": documents.len(),
+                "document_files": document_files.len(),
+                "visit_attachments": attachments.len(),
+                "visit_attachment_files": attachment_files.len(),
+                "notifications": notifications.len(),
+                "access_log_entries": access_log.len(),
+            },
+        });
+
+        entries.push(BundleEntry::json("manifest.json", &manifest)?);
+        entries.push(BundleEntry::json(
+            "patient.json",
+            &serde_json::json!({
+                "demographics": patient,
+                "insurance": insurance,
+                "notification_preferences": preferences,
+            }),
+        )?);
+        entries.push(BundleEntry::json(
+            "visits.json",
+            &serde_json::to_value(&visits)?,
+        )?);
+        entries.push(BundleEntry::json(
+            "diagnoses.json",
+            &serde_json::to_value(&diagnoses)?,
+        )?);
+        entries.push(BundleEntry::json(
+            "prescriptions.json",
+            &serde_json::to_value(&prescriptions)?,
+        )?);
+        entries.push(BundleEntry::json(
+            "appointments.json",
+            &serde_json::to_value(&appointments)?,
+        )?);
+        entries.push(BundleEntry::json(
+            "documents.json",
+            &serde_json::Value::Array(documents_json),
+        )?);
+        entries.push(BundleEntry::json(
+            "visit_attachments.json",
+            &serde_json::Value::Array(attachments_json),
+        )?);
+        entries.push(BundleEntry::json(
+            "notifications.json",
+            &serde_json::Value::Array(notifications),
+        )?);
+        entries.push(BundleEntry::json(
+            "access_log.json",
+            &serde_json::to_value(&access_log)?,
+        )?);
+        entries.extend(document_files);
+        entries.extend(attachment_files);
+
+        Ok(entries)
+    }
+
+    /// Load notifications, notification preferences and generated documents
+    async fn load_patient_rows(
+        &self,
+        patient_id: Uuid,
+        requested_by: Uuid,
+    ) -> Result<(
+        Vec<serde_json::Value>,
+        Option<serde_json::Value>,
+        Vec<ExportDocumentRow>,
+    )> {
+        let mut tx = begin_with_rls(&self.pool, requested_by)
+            .await
+            .context("Failed to begin transaction")?;
+
+        let notifications: Vec<serde_json::Value> = sqlx::query_scalar(
+            r#"
+            SELECT jsonb_build_object(
+                'id', id,
+                'appointment_id', appointment_id,
+                'notification_type', notification_type,
+                'delivery_method', delivery_method,
+                'recipient_email', recipient_email,
+                'recipient_phone', recipient_phone,
+                'recipient_name', recipient_name,
+                'subject', subject,
+                'message_body', message_body,
+                'scheduled_for', scheduled_for,
+                'status', status,
+                'sent_at', sent_at,
+                'created_at', created_at
+            )
+            FROM notification_queue
+            WHERE patient_id = $1
+            ORDER BY created_at
+            "#,
+        )
+        .bind(patient_id)
+        .fetch_all(&mut *tx)
+        .await
+        .context("Failed to fetch patient notifications")?;
+
+        let preferences: Option<serde_json::Value> = sqlx::query_scalar(
+            r#"
+            SELECT to_jsonb(p) - 'id' - 'patient_id'
+            FROM patient_notification_preferences p
+            WHERE patient_id = $1
+            "#,
+        )
+        .bind(patient_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .context("Failed to fetch patient notification preferences")?;
+
+        let documents = sqlx::query_as::<_, ExportDocumentRow>(
+            r#"
+            SELECT id, visit_id, document_type, document_title, document_filename,
+                   file_path, status, is_signed, signed_at, created_at
+            FROM generated_documents
+            WHERE patient_id = $1 AND deleted_at IS NULL
+            ORDER BY created_at
+            "#,
+        )
+        .bind(patient_id)
+        .fetch_all(&mut *tx)
+        .await
+        .context("Failed to fetch patient documents")?;
+
+        tx.commit().await.context("Failed to commit transaction")?;
+
+        Ok((notifications, preferences, documents))
+    }
+
+    /// Load the full record access trail, oldest first
+    async fn load_access_log(
+        &self,
+        patient_id: Uuid,
+        requested_by: Uuid,
+    ) -> Result<Vec<PatientAccessEntry>> {
+        let service = AuditLogService::new(self.pool.clone());
+        let mut entries = Vec::new();
+        let mut page = 1;
+
+        loop {
+            let log = service
+                .get_patient_access_log(
+                    patient_id,
+                    PatientAccessLogFilter {
+                        date_from: None,
+                        date_to: None,
+                        user_id: None,
+                        page,
+                        page_size: PAGE_SIZE,
+                    },
+                    requested_by,
+                )
+                .await
+                .context("Failed to fetch patient access log")?;
+
+            entries.extend(log.entries);
+            if page >= log.total_pages {
+                break;
+            }
+            page += 1;
+        }
+
+        entries.reverse();
+        Ok(entries)
+    }
+}
+
+/// Name of a generated document inside the bundle
+///
+/// Prefixed with its position so documents sharing a filename don't collide,
+/// and restricted to a safe character set so names can't escape `documents/`.
+fn document_entry_name(index: usize, filename: &str) -> String {
+    format!(
+        "documents/{:03}_{}",
+        index + 1,
+        safe_entry_filename(filename, "document.pdf")
+    )
+}
+
+/// Name of a visit attachment inside the bundle (same rules as documents)
+fn attachment_entry_name(index: usize, filename: &str) -> String {
+    format!(
+        "visit_attachments/{:03}_{}",
+        index + 1,
+        safe_entry_filename(filename, "attachment")
+    )
+}
+
+fn safe_entry_filename(filename: &str, fallback: &str) -> String {
+    let sanitized: String = filename
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let sanitized = sanitized.trim_start_matches('.');
+    if sanitized.is_empty() {
+        fallback.to_string()
+    } else {
+        sanitized.to_string()
+    }
+}
+
+/// Write the entries to an AES-256 encrypted zip at `path`
+fn write_encrypted_zip(path: &Path, entries: &[BundleEntry], passphrase: &str) -> Result<()> {
+    let file = std::fs::File::create(path).context("Failed to create patient export file")?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .with_aes_encryption(AesMode::Aes256, passphrase);
+
+    for entry in entries {
+        zip.start_file(entry.name.as_str(), options)
+            .with_context(|| format!("Failed to add {} to patient export", entry.name))?;
+        zip.write_all(&entry.data)
+            .with_context(|| format!("Failed to write {} to patient export", entry.name))?;
+    }
+
+    zip.finish().context("Failed to finish patient export")?;
+
+    Ok(())
+}
+
+/// Spawn the background task deleting expired patient exports
+pub fn spawn_patient_export_cleanup(
+    pool: PgPool,
+    encryption_key: EncryptionKey,
+    storage_path: PathBuf,
+) {
+    let service = PatientExportService::new(pool, encryption_key, storage_path);
+
+    tokio::spawn(async move {
+        loop {
+            match service.purge_expired().await {
+                Ok(0) => {}
+                Ok(count) => info!("Deleted {} expired patient exports", count),
+                Err(e) => error!("Patient export cleanup failed: {:#}", e),
+            }
+
+            sleep(TokioDuration::from_secs(CLEANUP_INTERVAL_MINUTES * 60)).await;
+        }
+    });
+
+    info!("Patient export cleanup spawned as background task");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn test_document_entry_name() {
+        assert_eq!(
+            document_entry_name(0, "referto_20260301.pdf"),
+            "documents/001_referto_20260301.pdf"
+        );
+        assert_eq!(
+            document_entry_name(11, "../../etc/passwd"),
+            "documents/012__.._etc_passwd"
+        );
+        assert_eq!(document_entry_name(2, ""), "documents/003_document.pdf");
+        assert_eq!(
+            attachment_entry_name(4, "lesione avambraccio.jpg"),
+            "visit_attachments/005_lesione_avambraccio.jpg"
+        );
+    }
+
+    #[test]
+    fn test_write_encrypted_zip_requires_passphrase() {
+        let path = std::env::temp_dir().join(format!("patient_export_test_{}.zip", Uuid::new_v4()));
+        let entries = vec![
+            BundleEntry::json("manifest.json", &serde_json::json!({"format_version": 1})).unwrap(),
+            BundleEntry {
+                name: "documents/001_a.pdf".to_string(),
+                data: b"%PDF-1.7".to_vec(),
+            },
+        ];
+
+        write_encrypted_zip(&path, &entries, "correct horse battery staple").unwrap();
+
+        let mut archive = zip::ZipArchive::new(std::fs::File::open(&path).unwrap()).unwrap();
+        assert_eq!(archive.len(), 2);
+        assert!(archive.by_name("manifest.json").is_err());
+        assert!(archive
+            .by_name_decrypt("manifest.json", b"wrong passphrase")
+            .is_err());
+
+        let mut contents = String::new();
+        archive
+            .by_name_decrypt("manifest.json", b"correct horse battery staple")
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert!(contents.contains("format_version"));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
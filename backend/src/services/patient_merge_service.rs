@@ -0,0 +1,356 @@
+/*!
+ * Patient Merge Service
+ *
+ * Merges a duplicate patient record (as surfaced by duplicate detection in
+ * `PatientService`) into the surviving record, in a single RLS transaction:
+ * - appointments, visits (signed and locked included), visit diagnoses,
+ *   prescriptions (with their e-prescription events), generated documents,
+ *   attachments, allergies, lab orders and results, notifications, the
+ *   communication log and message threads are re-parented
+ * - problems are re-parented unless the surviving patient already has the
+ *   same condition active; the visit links of such a problem move to the
+ *   surviving patient's entry
+ * - insurance is re-parented unless the surviving patient already has
+ *   insurance of the same type
+ * - the duplicate is tombstoned (`merged_into_id`, status INACTIVE); a
+ *   trigger makes it read-only
+ *
+ * Consents, notification preferences, access delegations and subject access
+ * exports stay with the duplicate.
+ */
+
+use crate::{
+    db::rls::begin_with_rls,
+    models::{MergePatientsRequest, PatientMergeResponse, PatientMergeSummary},
+    utils::{AppError, Result},
+};
+use sqlx::{PgConnection, PgPool};
+use tracing::info;
+use uuid::Uuid;
+
+/// Patient row fields needed to merge
+#[derive(Debug, sqlx::FromRow)]
+struct MergePatientRow {
+    id: Uuid,
+    medical_record_number: String,
+    anonymized_at: Option<chrono::DateTime<chrono::Utc>>,
+    merged_into_id: Option<Uuid>,
+}
+
+/// Patient merge service
+pub struct PatientMergeService {
+    pool: PgPool,
+}
+
+impl PatientMergeService {
+    /// Create a new patient merge service
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Merge `merge_id` into `keep_id`
+    ///
+    /// Irreversible. The caller must confirm the duplicate's medical record
+    /// number. Fails with Conflict if either patient is anonymized, already
+    /// merged, or has a pending erasure request.
+    pub async fn merge_patients(
+        &self,
+        keep_id: Uuid,
+        merge_id: Uuid,
+        req: &MergePatientsRequest,
+        merged_by: Uuid,
+    ) -> Result<PatientMergeResponse> {
+        if keep_id == merge_id {
+            return Err(AppError::Validation(
+                "A patient cannot be merged into itself".to_string(),
+            ));
+        }
+
+        let mut tx = begin_with_rls(&self.pool, merged_by).await?;
+
+        // Both rows locked in id order, so concurrent merges cannot deadlock
+        let patients = sqlx::query_as::<_, MergePatientRow>(
+            r#"
+            SELECT id, medical_record_number, anonymized_at, merged_into_id
+            FROM patients
+            WHERE id = ANY($1)
+            ORDER BY id
+            FOR UPDATE
+            "#,
+        )
+        .bind(vec![keep_id, merge_id])
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let find = |id: Uuid| {
+            patients
+                .iter()
+                .find(|p| p.id == id)
+                .ok_or_else(|| AppError::NotFound(format!("Patient {} not found", id)))
+        };
+        let keep = find(keep_id)?;
+        let duplicate = find(merge_id)?;
+
+        for patient in [keep, duplicate] {
+            if patient.anonymized_at.is_some() {
+                return Err(AppError::Conflict(format!(
+                    "Patient {} has been anonymized",
+                    patient.id
+                )));
+            }
+            if let Some(merged_into) = patient.merged_into_id {
+                return Err(AppError::Conflict(format!(
+                    "Patient {} has already been merged into {}",
+                    patient.id, merged_into
+                )));
+            }
+        }
+
+        if duplicate.medical_record_number != req.confirm_medical_record_number.trim() {
+            return Err(AppError::Validation(
+                "Medical record number does not match the patient being merged".to_string(),
+            ));
+        }
+
+        let pending_erasure: bool = sqlx::query_scalar(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM patient_erasure_requests
+                WHERE patient_id = ANY($1) AND status = 'PENDING'
+            )
+            "#,
+        )
+        .bind(vec![keep_id, merge_id])
+        .fetch_one(&mut *tx)
+        .await?;
+        if pending_erasure {
+            return Err(AppError::Conflict(
+                "A patient being merged has a pending erasure request".to_string(),
+            ));
+        }
+
+        // Scopes the merge RLS policies and the signed-visit exception to
+        // this pair for this transaction
+        sqlx::query(
+            r#"
+            SELECT set_config('app.merge_patient_id', $1, true),
+                   set_config('app.merge_into_patient_id', $2, true)
+            "#,
+        )
+        .bind(merge_id.to_string())
+        .bind(keep_id.to_string())
+        .execute(&mut *tx)
+        .await?;
+
+        let appointments = self
+            .reparent(&mut tx, "appointments", merge_id, keep_id)
+            .await?;
+        let visits = self.reparent(&mut tx, "visits", merge_id, keep_id).await?;
+        let visit_diagnoses = self
+            .reparent(&mut tx, "visit_diagnoses", merge_id, keep_id)
+            .await?;
+        self.reparent(&mut tx, "visit_calculations", merge_id, keep_id)
+            .await?;
+        self.reparent(&mut tx, "visit_addenda", merge_id, keep_id)
+            .await?;
+        self.reparent(&mut tx, "visit_attachments", merge_id, keep_id)
+            .await?;
+        let prescriptions = self
+            .reparent(&mut tx, "prescriptions", merge_id, keep_id)
+            .await?;
+        self.reparent(&mut tx, "prescription_allergy_overrides", merge_id, keep_id)
+            .await?;
+        self.reparent(&mut tx, "e_prescription_events", merge_id, keep_id)
+            .await?;
+        let documents = self
+            .reparent(&mut tx, "generated_documents", merge_id, keep_id)
+            .await?;
+        let attachments = self
+            .reparent(&mut tx, "patient_attachments", merge_id, keep_id)
+            .await?;
+        let allergies = self
+            .reparent(&mut tx, "patient_allergies", merge_id, keep_id)
+            .await?;
+
+        // One active problem per condition: the surviving patient's entry
+        // takes the visit links of the duplicate's
+        sqlx::query(
+            r#"
+            INSERT INTO patient_problem_visits (problem_id, visit_id, visit_date, linked_by)
+            SELECT kept.id, link.visit_id, link.visit_date, $3
+            FROM patient_problems dup
+            JOIN patient_problems kept
+              ON kept.patient_id = $2
+             AND kept.icd10_code = dup.icd10_code
+             AND kept.status = 'ACTIVE' AND kept.deleted_at IS NULL
+            JOIN patient_problem_visits link ON link.problem_id = dup.id
+            WHERE dup.patient_id = $1 AND dup.status = 'ACTIVE' AND dup.deleted_at IS NULL
+            ON CONFLICT (problem_id, visit_id) DO NOTHING
+            "#,
+        )
+        .bind(merge_id)
+        .bind(keep_id)
+        .bind(merged_by)
+        .execute(&mut *tx)
+        .await?;
+
+        let problems = sqlx::query(
+            r#"
+            UPDATE patient_problems dup
+            SET patient_id = $2, updated_by = $3
+            WHERE dup.patient_id = $1
+              AND NOT (
+                  dup.status = 'ACTIVE' AND dup.deleted_at IS NULL
+                  AND EXISTS (
+                      SELECT 1 FROM patient_problems kept
+                      WHERE kept.patient_id = $2
+                        AND kept.icd10_code = dup.icd10_code
+                        AND kept.status = 'ACTIVE' AND kept.deleted_at IS NULL
+                  )
+              )
+            "#,
+        )
+        .bind(merge_id)
+        .bind(keep_id)
+        .bind(merged_by)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        let lab_orders = self
+            .reparent(&mut tx, "lab_orders", merge_id, keep_id)
+            .await?;
+        let lab_results = self
+            .reparent(&mut tx, "lab_results", merge_id, keep_id)
+            .await?;
+        let imaging_orders = self
+            .reparent(&mut tx, "imaging_orders", merge_id, keep_id)
+            .await?;
+        let referrals = self
+            .reparent(&mut tx, "referrals", merge_id, keep_id)
+            .await?;
+        let notifications = self
+            .reparent(&mut tx, "notification_queue", merge_id, keep_id)
+            .await?;
+        let communications = self
+            .reparent(&mut tx, "patient_communications", merge_id, keep_id)
+            .await?;
+        let message_threads = self
+            .reparent(&mut tx, "patient_message_threads", merge_id, keep_id)
+            .await?;
+        let visit_surveys = self
+            .reparent(&mut tx, "visit_surveys", merge_id, keep_id)
+            .await?;
+        let televisit_sessions = self
+            .reparent(&mut tx, "televisit_sessions", merge_id, keep_id)
+            .await?;
+        let waitlist_entries = self
+            .reparent(&mut tx, "waitlist_entries", merge_id, keep_id)
+            .await?;
+        self.reparent(&mut tx, "waitlist_offers", merge_id, keep_id)
+            .await?;
+
+        // One insurance per type and patient: the surviving patient's wins
+        let insurance = sqlx::query(
+            r#"
+            UPDATE patient_insurance
+            SET patient_id = $2, updated_by = $3
+            WHERE patient_id = $1
+              AND insurance_type NOT IN (
+                  SELECT insurance_type FROM patient_insurance WHERE patient_id = $2
+              )
+            "#,
+        )
+        .bind(merge_id)
+        .bind(keep_id)
+        .bind(merged_by)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        let insurance_not_moved = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM patient_insurance WHERE patient_id = $1",
+        )
+        .bind(merge_id)
+        .fetch_one(&mut *tx)
+        .await? as u64;
+
+        // The duplicate goes last: once merged_at is set it is read-only
+        let merged_at: chrono::DateTime<chrono::Utc> = sqlx::query_scalar(
+            r#"
+            UPDATE patients
+            SET status = CASE WHEN status = 'DECEASED' THEN status ELSE 'INACTIVE' END,
+                merged_into_id = $2,
+                merged_at = NOW(),
+                merged_by = $3,
+                updated_at = NOW(),
+                updated_by = $3
+            WHERE id = $1
+            RETURNING merged_at
+            "#,
+        )
+        .bind(merge_id)
+        .bind(keep_id)
+        .bind(merged_by)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let response = PatientMergeResponse {
+            surviving_patient_id: keep_id,
+            surviving_medical_record_number: keep.medical_record_number.clone(),
+            merged_patient_id: merge_id,
+            merged_medical_record_number: duplicate.medical_record_number.clone(),
+            merged_at,
+            merged_by,
+            summary: PatientMergeSummary {
+                appointments,
+                visits,
+                visit_diagnoses,
+                prescriptions,
+                documents,
+                attachments,
+                allergies,
+                problems,
+                lab_orders,
+                lab_results,
+                imaging_orders,
+                referrals,
+                insurance,
+                insurance_not_moved,
+                notifications,
+                communications,
+                message_threads,
+                visit_surveys,
+                televisit_sessions,
+                waitlist_entries,
+            },
+        };
+
+        tx.commit().await?;
+
+        info!("Patient {} merged into {}", merge_id, keep_id);
+
+        Ok(response)
+    }
+
+    /// Move every row of `table` from the duplicate to the surviving patient
+    async fn reparent(
+        &self,
+        tx: &mut PgConnection,
+        table: &'static str,
+        from: Uuid,
+        to: Uuid,
+    ) -> Result<u64> {
+        let moved = sqlx::query(&format!(
+            "UPDATE {} SET patient_id = $2 WHERE patient_id = $1",
+            table
+        ))
+        .bind(from)
+        .bind(to)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        Ok(moved)
+    }
+}
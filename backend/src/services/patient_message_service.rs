@@ -0,0 +1,625 @@
+/*!
+ * Patient Message Service
+ *
+ * Secure practice-patient conversations, under strict RLS: only doctors
+ * (and admins) and nurses read and write them, and staff only write
+ * messages in the practice's name, as themselves. Subjects and bodies are
+ * encrypted.
+ *
+ * The provider inbox lists every conversation of the practice, most recent
+ * activity first, with the number of patient messages nobody has read yet.
+ * Opening a conversation does not mark it read: `mark_read` records the
+ * read receipt of the patient messages, with the staff member who read
+ * them.
+ *
+ * Attachments are documents already on the patient record; the patient
+ * portal stores what patients send as patient attachments too.
+ */
+
+use std::collections::HashMap;
+
+use crate::{
+    db::rls::begin_with_rls,
+    models::{
+        CreateMessageThreadRequest, CreatePatientMessageRequest, MessageAttachmentResponse,
+        MessageInboxQuery, MessageInboxResponse, MessageSender, MessageThreadDetailResponse,
+        MessageThreadResponse, MessageThreadStatus, MessageUnreadCountResponse, PatientMessage,
+        PatientMessageAttachment, PatientMessageResponse, PatientMessageThread,
+        UpdateMessageThreadRequest,
+    },
+    utils::{encryption::EncryptionKey, AppError, Result},
+};
+use sqlx::{PgConnection, PgPool};
+use tracing::info;
+use uuid::Uuid;
+
+const THREAD_SELECT: &str = r#"
+    SELECT t.id, t.patient_id, t.subject, t.status, t.assigned_to, t.last_message_at,
+           t.closed_at, t.closed_by, t.created_by, t.created_at, t.updated_at,
+           (SELECT COUNT(*) FROM patient_messages m
+            WHERE m.thread_id = t.id AND m.sender = 'PATIENT'
+              AND m.read_by_practice_at IS NULL) AS unread_count
+    FROM patient_message_threads t
+"#;
+
+const MESSAGE_COLUMNS: &str = r#"
+    id, thread_id, sender, sender_user_id, body, created_at, read_by_patient_at,
+    read_by_practice_at, read_by
+"#;
+
+/// Inbox filters, shared by the page and its count
+const INBOX_FILTER: &str = r#"
+    WHERE ($1::TEXT IS NULL OR t.status = $1)
+      AND ($2::UUID IS NULL OR t.patient_id = $2)
+      AND ($3::UUID IS NULL OR t.assigned_to = $3)
+      AND (NOT $4 OR EXISTS (
+          SELECT 1 FROM patient_messages m
+          WHERE m.thread_id = t.id AND m.sender = 'PATIENT' AND m.read_by_practice_at IS NULL
+      ))
+"#;
+
+/// Patient message service
+pub struct PatientMessageService {
+    pool: PgPool,
+    encryption_key: EncryptionKey,
+}
+
+impl PatientMessageService {
+    /// Create a new patient message service
+    pub fn new(pool: PgPool, encryption_key: EncryptionKey) -> Self {
+        Self {
+            pool,
+            encryption_key,
+        }
+    }
+
+    /// Provider inbox: conversations of every patient, most recent activity
+    /// first
+    pub async fn inbox(
+        &self,
+        query: &MessageInboxQuery,
+        user_id: Uuid,
+    ) -> Result<MessageInboxResponse> {
+        let limit = query.limit();
+        let offset = query.offset();
+        let status = query.status.map(|s| s.as_str());
+        let assigned_to = if query.assigned_to_me {
+            Some(user_id)
+        } else {
+            query.assigned_to
+        };
+
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        let threads = sqlx::query_as::<_, PatientMessageThread>(&format!(
+            "{} {} ORDER BY t.last_message_at DESC LIMIT $5 OFFSET $6",
+            THREAD_SELECT, INBOX_FILTER
+        ))
+        .bind(status)
+        .bind(query.patient_id)
+        .bind(assigned_to)
+        .bind(query.unread_only)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let total: i64 = sqlx::query_scalar(&format!(
+            "SELECT COUNT(*) FROM patient_message_threads t {}",
+            INBOX_FILTER
+        ))
+        .bind(status)
+        .bind(query.patient_id)
+        .bind(assigned_to)
+        .bind(query.unread_only)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let threads = self.thread_responses(&mut tx, threads).await?;
+        tx.commit().await?;
+
+        Ok(MessageInboxResponse {
+            threads,
+            total,
+            limit,
+            offset,
+        })
+    }
+
+    /// Unread patient messages across the practice, and in threads assigned
+    /// to the caller
+    pub async fn unread_count(&self, user_id: Uuid) -> Result<MessageUnreadCountResponse> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        let (threads, messages, assigned_to_me): (i64, i64, i64) = sqlx::query_as(
+            r#"
+            SELECT COUNT(DISTINCT m.thread_id),
+                   COUNT(*),
+                   COUNT(*) FILTER (WHERE t.assigned_to = $1)
+            FROM patient_messages m
+            JOIN patient_message_threads t ON t.id = m.thread_id
+            WHERE m.sender = 'PATIENT' AND m.read_by_practice_at IS NULL
+            "#,
+        )
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(MessageUnreadCountResponse {
+            threads,
+            messages,
+            assigned_to_me,
+        })
+    }
+
+    /// A patient's conversations, most recent activity first
+    pub async fn list_patient_threads(
+        &self,
+        patient_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Vec<MessageThreadResponse>> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        let threads = sqlx::query_as::<_, PatientMessageThread>(&format!(
+            "{} WHERE t.patient_id = $1 ORDER BY t.last_message_at DESC",
+            THREAD_SELECT
+        ))
+        .bind(patient_id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let threads = self.thread_responses(&mut tx, threads).await?;
+        tx.commit().await?;
+
+        Ok(threads)
+    }
+
+    /// Get a conversation with its messages, oldest first
+    pub async fn get_thread(
+        &self,
+        thread_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<MessageThreadDetailResponse> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        let detail = self.detail(&mut tx, thread_id).await?;
+        tx.commit().await?;
+
+        Ok(detail)
+    }
+
+    /// Start a conversation with a patient
+    ///
+    /// Fails with Validation if an attachment is not one of the patient's
+    /// documents or the assignee is not active clinical staff, and with
+    /// Conflict if the patient is anonymized or merged.
+    pub async fn create_thread(
+        &self,
+        patient_id: Uuid,
+        req: &CreateMessageThreadRequest,
+        user_id: Uuid,
+    ) -> Result<MessageThreadDetailResponse> {
+        let subject = self.encrypt(req.subject.trim())?;
+
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        self.check_patient(&mut tx, patient_id).await?;
+        if let Some(assignee) = req.assigned_to {
+            self.check_assignee(&mut tx, assignee).await?;
+        }
+
+        let thread_id: Uuid = sqlx::query_scalar(
+            r#"
+            INSERT INTO patient_message_threads (patient_id, subject, assigned_to, created_by)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id
+            "#,
+        )
+        .bind(patient_id)
+        .bind(subject)
+        .bind(req.assigned_to)
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        self.insert_message(
+            &mut tx,
+            thread_id,
+            patient_id,
+            &req.body,
+            &req.attachment_ids,
+            user_id,
+        )
+        .await?;
+
+        let detail = self.detail(&mut tx, thread_id).await?;
+        tx.commit().await?;
+
+        info!(
+            "Message thread {} started with patient {} by {}",
+            thread_id, patient_id, user_id
+        );
+
+        Ok(detail)
+    }
+
+    /// Reply in a conversation, reopening it if it was closed
+    ///
+    /// Fails with Validation if an attachment is not one of the patient's
+    /// documents, and with Conflict if the patient has since been anonymized
+    /// or merged.
+    pub async fn reply(
+        &self,
+        thread_id: Uuid,
+        req: &CreatePatientMessageRequest,
+        user_id: Uuid,
+    ) -> Result<PatientMessageResponse> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        let thread = self.find(&mut tx, thread_id).await?;
+        self.check_patient(&mut tx, thread.patient_id).await?;
+
+        let message = self
+            .insert_message(
+                &mut tx,
+                thread_id,
+                thread.patient_id,
+                &req.body,
+                &req.attachment_ids,
+                user_id,
+            )
+            .await?;
+
+        tx.commit().await?;
+
+        info!(
+            "Message {} sent in thread {} by {}",
+            message.id, thread_id, user_id
+        );
+
+        Ok(message)
+    }
+
+    /// Record the read receipt of the conversation's unread patient messages
+    ///
+    /// Returns the number of messages marked read.
+    pub async fn mark_read(&self, thread_id: Uuid, user_id: Uuid) -> Result<u64> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        self.find(&mut tx, thread_id).await?;
+
+        let marked = sqlx::query(
+            r#"
+            UPDATE patient_messages
+            SET read_by_practice_at = NOW(), read_by = $2
+            WHERE thread_id = $1 AND sender = 'PATIENT' AND read_by_practice_at IS NULL
+            "#,
+        )
+        .bind(thread_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        tx.commit().await?;
+
+        Ok(marked)
+    }
+
+    /// Close, reopen or (re)assign a conversation
+    ///
+    /// Fails with Validation if the assignee is not active clinical staff.
+    pub async fn update_thread(
+        &self,
+        thread_id: Uuid,
+        req: &UpdateMessageThreadRequest,
+        user_id: Uuid,
+    ) -> Result<MessageThreadResponse> {
+        if req.unassign && req.assigned_to.is_some() {
+            return Err(AppError::Validation(
+                "Give either assigned_to or unassign, not both".to_string(),
+            ));
+        }
+
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        let thread = self.find(&mut tx, thread_id).await?;
+        if let Some(assignee) = req.assigned_to {
+            self.check_assignee(&mut tx, assignee).await?;
+        }
+
+        let assigned_to = if req.unassign {
+            None
+        } else {
+            req.assigned_to.or(thread.assigned_to)
+        };
+        let status = req
+            .status
+            .map(|s| s.as_str())
+            .unwrap_or(thread.status.as_str());
+
+        sqlx::query(
+            r#"
+            UPDATE patient_message_threads
+            SET status = $2,
+                assigned_to = $3,
+                closed_at = CASE WHEN $2 = 'CLOSED' THEN COALESCE(closed_at, NOW()) END,
+                closed_by = CASE WHEN $2 = 'CLOSED' THEN COALESCE(closed_by, $4) END,
+                updated_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(thread_id)
+        .bind(status)
+        .bind(assigned_to)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+        let thread = self.find(&mut tx, thread_id).await?;
+        let mut responses = self.thread_responses(&mut tx, vec![thread]).await?;
+        tx.commit().await?;
+
+        Ok(responses.remove(0))
+    }
+
+    /// Insert a practice message with its attachments and bump the thread
+    async fn insert_message(
+        &self,
+        tx: &mut PgConnection,
+        thread_id: Uuid,
+        patient_id: Uuid,
+        body: &str,
+        attachment_ids: &[Uuid],
+        user_id: Uuid,
+    ) -> Result<PatientMessageResponse> {
+        let mut attachment_ids = attachment_ids.to_vec();
+        attachment_ids.sort();
+        attachment_ids.dedup();
+
+        let found: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*) FROM patient_attachments
+            WHERE id = ANY($1) AND patient_id = $2 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(&attachment_ids)
+        .bind(patient_id)
+        .fetch_one(&mut *tx)
+        .await?;
+        if found as usize != attachment_ids.len() {
+            return Err(AppError::Validation(
+                "Attachments must be documents of the patient's record".to_string(),
+            ));
+        }
+
+        let message = sqlx::query_as::<_, PatientMessage>(&format!(
+            r#"
+            INSERT INTO patient_messages (thread_id, sender, sender_user_id, body)
+            VALUES ($1, $2, $3, $4)
+            RETURNING {}
+            "#,
+            MESSAGE_COLUMNS
+        ))
+        .bind(thread_id)
+        .bind(MessageSender::Practice.as_str())
+        .bind(user_id)
+        .bind(self.encrypt(body.trim())?)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO patient_message_attachments (message_id, attachment_id)
+            SELECT $1, UNNEST($2::UUID[])
+            "#,
+        )
+        .bind(message.id)
+        .bind(&attachment_ids)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            UPDATE patient_message_threads
+            SET last_message_at = $2, status = $3, closed_at = NULL, closed_by = NULL,
+                updated_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(thread_id)
+        .bind(message.created_at)
+        .bind(MessageThreadStatus::Open.as_str())
+        .execute(&mut *tx)
+        .await?;
+
+        let attachments = self.attachments(tx, &[message.id]).await?;
+        self.message_response(&message, &attachments)
+    }
+
+    async fn find(&self, tx: &mut PgConnection, thread_id: Uuid) -> Result<PatientMessageThread> {
+        sqlx::query_as::<_, PatientMessageThread>(&format!("{} WHERE t.id = $1", THREAD_SELECT))
+            .bind(thread_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Message thread {} not found", thread_id)))
+    }
+
+    async fn detail(
+        &self,
+        tx: &mut PgConnection,
+        thread_id: Uuid,
+    ) -> Result<MessageThreadDetailResponse> {
+        let thread = self.find(tx, thread_id).await?;
+
+        let messages = sqlx::query_as::<_, PatientMessage>(&format!(
+            "SELECT {} FROM patient_messages WHERE thread_id = $1 ORDER BY created_at",
+            MESSAGE_COLUMNS
+        ))
+        .bind(thread_id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let message_ids: Vec<Uuid> = messages.iter().map(|m| m.id).collect();
+        let attachments = self.attachments(tx, &message_ids).await?;
+        let messages = messages
+            .iter()
+            .map(|message| self.message_response(message, &attachments))
+            .collect::<Result<Vec<_>>>()?;
+
+        let thread = self.thread_responses(tx, vec![thread]).await?.remove(0);
+
+        Ok(MessageThreadDetailResponse { thread, messages })
+    }
+
+    async fn attachments(
+        &self,
+        tx: &mut PgConnection,
+        message_ids: &[Uuid],
+    ) -> Result<Vec<PatientMessageAttachment>> {
+        let attachments = sqlx::query_as::<_, PatientMessageAttachment>(
+            r#"
+            SELECT ma.message_id, ma.attachment_id, a.patient_id, a.title,
+                   f.original_filename, f.mime_type, f.file_size_bytes
+            FROM patient_message_attachments ma
+            JOIN patient_attachments a ON a.id = ma.attachment_id
+            JOIN uploaded_files f ON f.id = a.file_id
+            WHERE ma.message_id = ANY($1) AND a.deleted_at IS NULL
+            ORDER BY a.title
+            "#,
+        )
+        .bind(message_ids)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        Ok(attachments)
+    }
+
+    /// Decrypt threads and add their patient's name
+    async fn thread_responses(
+        &self,
+        tx: &mut PgConnection,
+        threads: Vec<PatientMessageThread>,
+    ) -> Result<Vec<MessageThreadResponse>> {
+        let patient_ids: Vec<Uuid> = threads.iter().map(|t| t.patient_id).collect();
+        let names: Vec<(Uuid, String, String)> =
+            sqlx::query_as("SELECT id, first_name, last_name FROM patients WHERE id = ANY($1)")
+                .bind(&patient_ids)
+                .fetch_all(&mut *tx)
+                .await?;
+
+        let mut patient_names = HashMap::with_capacity(names.len());
+        for (id, first_name, last_name) in names {
+            let first_name = self.decrypt(&first_name)?;
+            let last_name = self.decrypt(&last_name)?;
+            patient_names.insert(id, format!("{} {}", first_name, last_name));
+        }
+
+        threads
+            .into_iter()
+            .map(|thread| {
+                Ok(MessageThreadResponse {
+                    patient_name: patient_names
+                        .get(&thread.patient_id)
+                        .cloned()
+                        .unwrap_or_default(),
+                    subject: self.decrypt(&thread.subject)?,
+                    id: thread.id,
+                    patient_id: thread.patient_id,
+                    status: thread.status,
+                    assigned_to: thread.assigned_to,
+                    last_message_at: thread.last_message_at,
+                    unread_count: thread.unread_count,
+                    closed_at: thread.closed_at,
+                    closed_by: thread.closed_by,
+                    created_by: thread.created_by,
+                    created_at: thread.created_at,
+                    updated_at: thread.updated_at,
+                })
+            })
+            .collect()
+    }
+
+    fn message_response(
+        &self,
+        message: &PatientMessage,
+        attachments: &[PatientMessageAttachment],
+    ) -> Result<PatientMessageResponse> {
+        Ok(PatientMessageResponse {
+            id: message.id,
+            thread_id: message.thread_id,
+            sender: message.sender.clone(),
+            sender_user_id: message.sender_user_id,
+            body: self.decrypt(&message.body)?,
+            attachments: attachments
+                .iter()
+                .filter(|a| a.message_id == message.id)
+                .cloned()
+                .map(MessageAttachmentResponse::from)
+                .collect(),
+            created_at: message.created_at,
+            read_by_patient_at: message.read_by_patient_at,
+            read_by_practice_at: message.read_by_practice_at,
+            read_by: message.read_by,
+        })
+    }
+
+    /// The patient must exist and be neither anonymized nor merged
+    async fn check_patient(&self, tx: &mut PgConnection, patient_id: Uuid) -> Result<()> {
+        let patient: Option<(
+            Option<chrono::DateTime<chrono::Utc>>,
+            Option<chrono::DateTime<chrono::Utc>>,
+        )> = sqlx::query_as("SELECT anonymized_at, merged_at FROM patients WHERE id = $1")
+            .bind(patient_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        match patient {
+            None => Err(AppError::NotFound(format!(
+                "Patient {} not found",
+                patient_id
+            ))),
+            Some((Some(_), _)) => Err(AppError::Conflict(format!(
+                "Patient {} has been anonymized",
+                patient_id
+            ))),
+            Some((_, Some(_))) => Err(AppError::Conflict(format!(
+                "Patient {} has been merged into another record",
+                patient_id
+            ))),
+            Some(_) => Ok(()),
+        }
+    }
+
+    /// Conversations are assigned to active doctors, nurses or admins
+    async fn check_assignee(&self, tx: &mut PgConnection, assignee: Uuid) -> Result<()> {
+        let eligible: bool = sqlx::query_scalar(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM users
+                WHERE id = $1 AND is_active = true AND role IN ('ADMIN', 'DOCTOR', 'NURSE')
+            )
+            "#,
+        )
+        .bind(assignee)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if !eligible {
+            return Err(AppError::Validation(
+                "Conversations can only be assigned to active doctors, nurses or admins"
+                    .to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn encrypt(&self, value: &str) -> Result<String> {
+        self.encryption_key
+            .encrypt(value)
+            .map_err(|e| AppError::Internal(format!("Failed to encrypt message: {}", e)))
+    }
+
+    fn decrypt(&self, value: &str) -> Result<String> {
+        self.encryption_key
+            .decrypt(value)
+            .map_err(|e| AppError::Internal(format!("Failed to decrypt message: {}", e)))
+    }
+}
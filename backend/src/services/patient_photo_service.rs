@@ -0,0 +1,401 @@
+/*!
+ * Patient Photo Service
+ *
+ * Identification photos on the patient record, stored through
+ * `FileUploadService` encrypted at rest:
+ * - the upload is size- and type-checked (JPEG, PNG or WebP, by magic bytes)
+ *   and decoded to build a JPEG thumbnail, which also rejects files that are
+ *   not really images
+ * - photo and thumbnail are referenced from `patients.photo_file_id` and
+ *   `patients.photo_thumbnail_file_id`; `photo_url` points to the photo
+ *   endpoint
+ * - a replaced or removed photo is deleted from storage
+ *
+ * Patient visibility and updates go through RLS; anonymized and merged
+ * patients cannot get a new photo.
+ */
+
+use crate::{
+    db::rls::begin_with_rls,
+    models::{
+        patient_photo::{
+            patient_photo_thumbnail_url, patient_photo_url, MAX_PATIENT_PHOTO_DIMENSION,
+        },
+        uploaded_file::{
+            FilePurpose, UploadedFile, MAX_PATIENT_PHOTO_SIZE, PATIENT_PHOTO_THUMBNAIL_SIZE,
+        },
+        PatientPhotoResponse,
+    },
+    services::FileUploadService,
+    utils::{encryption::EncryptionKey, AppError, Result},
+};
+use sqlx::{PgConnection, PgPool};
+use std::io::Cursor;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Patient row fields needed to change the photo
+#[derive(Debug, sqlx::FromRow)]
+struct PhotoPatientRow {
+    anonymized_at: Option<chrono::DateTime<chrono::Utc>>,
+    merged_at: Option<chrono::DateTime<chrono::Utc>>,
+    photo_file_id: Option<Uuid>,
+    photo_thumbnail_file_id: Option<Uuid>,
+}
+
+/// A decoded photo's JPEG thumbnail with the dimensions of both
+#[derive(Debug)]
+pub struct PhotoThumbnail {
+    pub content: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    pub thumbnail_width: u32,
+    pub thumbnail_height: u32,
+}
+
+/// Decode a photo and build its JPEG thumbnail
+///
+/// The thumbnail keeps the aspect ratio, with its longest side at most
+/// `PATIENT_PHOTO_THUMBNAIL_SIZE` pixels. CPU-bound: call from
+/// `spawn_blocking`.
+pub fn make_thumbnail(content: &[u8]) -> std::result::Result<PhotoThumbnail, String> {
+    let mut reader = image::ImageReader::new(Cursor::new(content))
+        .with_guessed_format()
+        .map_err(|e| e.to_string())?;
+    let mut limits = image::Limits::default();
+    limits.max_image_width = Some(MAX_PATIENT_PHOTO_DIMENSION);
+    limits.max_image_height = Some(MAX_PATIENT_PHOTO_DIMENSION);
+    reader.limits(limits);
+
+    let photo = reader.decode().map_err(|e| e.to_string())?;
+    // JPEG has no alpha channel
+    let thumbnail = image::DynamicImage::ImageRgb8(
+        photo
+            .thumbnail(PATIENT_PHOTO_THUMBNAIL_SIZE, PATIENT_PHOTO_THUMBNAIL_SIZE)
+            .to_rgb8(),
+    );
+
+    let mut jpeg = Vec::new();
+    thumbnail
+        .write_to(&mut Cursor::new(&mut jpeg), image::ImageFormat::Jpeg)
+        .map_err(|e| e.to_string())?;
+
+    Ok(PhotoThumbnail {
+        content: jpeg,
+        width: photo.width(),
+        height: photo.height(),
+        thumbnail_width: thumbnail.width(),
+        thumbnail_height: thumbnail.height(),
+    })
+}
+
+/// Patient photo service
+pub struct PatientPhotoService {
+    pool: PgPool,
+    encryption_key: EncryptionKey,
+}
+
+impl PatientPhotoService {
+    /// Create a new patient photo service
+    pub fn new(pool: PgPool, encryption_key: EncryptionKey) -> Self {
+        Self {
+            pool,
+            encryption_key,
+        }
+    }
+
+    /// Set or replace a patient's photo
+    ///
+    /// Fails with Validation if the file is too large, not a JPEG, PNG or
+    /// WebP image, or cannot be decoded, and with Conflict if the patient is
+    /// anonymized or merged.
+    pub async fn upload_photo(
+        &self,
+        patient_id: Uuid,
+        content: Vec<u8>,
+        original_filename: &str,
+        user_id: Uuid,
+    ) -> Result<PatientPhotoResponse> {
+        if content.len() > MAX_PATIENT_PHOTO_SIZE {
+            return Err(AppError::Validation(format!(
+                "Photo exceeds maximum size of {} MB",
+                MAX_PATIENT_PHOTO_SIZE / 1024 / 1024
+            )));
+        }
+
+        let validation = FileUploadService::validate_file(
+            &content,
+            original_filename,
+            FilePurpose::PatientPhoto,
+        );
+        if !validation.is_valid {
+            return Err(AppError::Validation(
+                validation
+                    .error_message
+                    .unwrap_or_else(|| "Invalid photo".to_string()),
+            ));
+        }
+
+        let content = std::sync::Arc::new(content);
+        let thumbnail = {
+            let content = content.clone();
+            tokio::task::spawn_blocking(move || make_thumbnail(&content))
+                .await
+                .map_err(|e| AppError::Internal(format!("Thumbnail task failed: {}", e)))?
+                .map_err(|e| {
+                    warn!("Patient photo could not be decoded: {}", e);
+                    AppError::Validation("Photo could not be decoded as an image".to_string())
+                })?
+        };
+
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        let previous = self.lock_patient(&mut tx, patient_id).await?;
+
+        let photo = self
+            .store(&content, original_filename, "Patient photo", user_id)
+            .await?;
+        let thumbnail_file = match self
+            .store(
+                &thumbnail.content,
+                "thumbnail.jpg",
+                "Patient photo thumbnail",
+                user_id,
+            )
+            .await
+        {
+            Ok(file) => file,
+            Err(e) => {
+                self.remove_files(patient_id, &[Some(photo.id)]).await;
+                return Err(e);
+            }
+        };
+
+        let attached = async {
+            sqlx::query(
+                r#"
+                UPDATE patients
+                SET photo_file_id = $2,
+                    photo_thumbnail_file_id = $3,
+                    photo_url = $4,
+                    updated_at = NOW(),
+                    updated_by = $5
+                WHERE id = $1
+                "#,
+            )
+            .bind(patient_id)
+            .bind(photo.id)
+            .bind(thumbnail_file.id)
+            .bind(patient_photo_url(patient_id))
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+            tx.commit().await
+        }
+        .await;
+
+        if let Err(e) = attached {
+            self.remove_files(patient_id, &[Some(photo.id), Some(thumbnail_file.id)])
+                .await;
+            return Err(e.into());
+        }
+
+        self.remove_files(
+            patient_id,
+            &[previous.photo_file_id, previous.photo_thumbnail_file_id],
+        )
+        .await;
+
+        info!("Photo of patient {} updated by {}", patient_id, user_id);
+
+        Ok(PatientPhotoResponse {
+            patient_id,
+            mime_type: photo.mime_type,
+            file_size_bytes: photo.file_size_bytes,
+            width: thumbnail.width,
+            height: thumbnail.height,
+            thumbnail_width: thumbnail.thumbnail_width,
+            thumbnail_height: thumbnail.thumbnail_height,
+            uploaded_at: photo.created_at,
+            url: patient_photo_url(patient_id),
+            thumbnail_url: patient_photo_thumbnail_url(patient_id),
+        })
+    }
+
+    /// Get a patient's photo (or its thumbnail) with the decrypted content
+    ///
+    /// Fails with NotFound if the patient is not visible to the user or has
+    /// no photo.
+    pub async fn get_photo(
+        &self,
+        patient_id: Uuid,
+        user_id: Uuid,
+        thumbnail: bool,
+    ) -> Result<(UploadedFile, Vec<u8>)> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        let file_id: Option<Option<Uuid>> = sqlx::query_scalar(
+            r#"
+            SELECT CASE WHEN $2 THEN photo_thumbnail_file_id ELSE photo_file_id END
+            FROM patients
+            WHERE id = $1
+            "#,
+        )
+        .bind(patient_id)
+        .bind(thumbnail)
+        .fetch_optional(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        let file_id = file_id
+            .ok_or_else(|| AppError::NotFound(format!("Patient {} not found", patient_id)))?
+            .ok_or_else(|| AppError::NotFound("Patient has no photo".to_string()))?;
+
+        FileUploadService::get_decrypted_file_with_content(
+            &self.pool,
+            file_id,
+            &self.encryption_key,
+        )
+        .await
+        .map_err(|e| {
+            warn!("Failed to read photo of patient {}: {}", patient_id, e);
+            AppError::Internal("Failed to read patient photo".to_string())
+        })?
+        .ok_or_else(|| AppError::NotFound("Patient has no photo".to_string()))
+    }
+
+    /// Remove a patient's photo
+    ///
+    /// Fails with NotFound if the patient has no photo.
+    pub async fn delete_photo(&self, patient_id: Uuid, user_id: Uuid) -> Result<()> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        let previous = self.lock_patient(&mut tx, patient_id).await?;
+
+        if previous.photo_file_id.is_none() && previous.photo_thumbnail_file_id.is_none() {
+            return Err(AppError::NotFound("Patient has no photo".to_string()));
+        }
+
+        sqlx::query(
+            r#"
+            UPDATE patients
+            SET photo_file_id = NULL,
+                photo_thumbnail_file_id = NULL,
+                photo_url = NULL,
+                updated_at = NOW(),
+                updated_by = $2
+            WHERE id = $1
+            "#,
+        )
+        .bind(patient_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        self.remove_files(
+            patient_id,
+            &[previous.photo_file_id, previous.photo_thumbnail_file_id],
+        )
+        .await;
+
+        info!("Photo of patient {} removed by {}", patient_id, user_id);
+
+        Ok(())
+    }
+
+    /// Lock the patient row, rejecting anonymized and merged patients
+    async fn lock_patient(
+        &self,
+        tx: &mut PgConnection,
+        patient_id: Uuid,
+    ) -> Result<PhotoPatientRow> {
+        let patient = sqlx::query_as::<_, PhotoPatientRow>(
+            r#"
+            SELECT anonymized_at, merged_at, photo_file_id, photo_thumbnail_file_id
+            FROM patients
+            WHERE id = $1
+            FOR UPDATE
+            "#,
+        )
+        .bind(patient_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Patient {} not found", patient_id)))?;
+
+        if patient.anonymized_at.is_some() {
+            return Err(AppError::Conflict(format!(
+                "Patient {} has been anonymized",
+                patient_id
+            )));
+        }
+        if patient.merged_at.is_some() {
+            return Err(AppError::Conflict(format!(
+                "Patient {} has been merged into another record",
+                patient_id
+            )));
+        }
+
+        Ok(patient)
+    }
+
+    async fn store(
+        &self,
+        content: &[u8],
+        original_filename: &str,
+        description: &str,
+        user_id: Uuid,
+    ) -> Result<UploadedFile> {
+        FileUploadService::upload_encrypted_file(
+            &self.pool,
+            content,
+            original_filename,
+            FilePurpose::PatientPhoto,
+            Some(description.to_string()),
+            user_id,
+            &self.encryption_key,
+        )
+        .await
+        .map_err(|e| {
+            warn!("Failed to store patient photo: {}", e);
+            AppError::Internal("Failed to store patient photo".to_string())
+        })
+    }
+
+    /// Delete photo files from storage; failures are only logged
+    async fn remove_files(&self, patient_id: Uuid, file_ids: &[Option<Uuid>]) {
+        for file_id in file_ids.iter().flatten() {
+            if let Err(e) = FileUploadService::hard_delete_file(&self.pool, *file_id).await {
+                warn!(
+                    "Failed to remove photo file {} of patient {}: {}",
+                    file_id, patient_id, e
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let image = image::RgbaImage::from_pixel(width, height, image::Rgba([200, 40, 40, 255]));
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgba8(image)
+            .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        png
+    }
+
+    #[test]
+    fn test_make_thumbnail_keeps_aspect_ratio() {
+        let thumbnail = make_thumbnail(&png(1024, 512)).unwrap();
+        assert_eq!((thumbnail.width, thumbnail.height), (1024, 512));
+        assert_eq!(thumbnail.thumbnail_width, PATIENT_PHOTO_THUMBNAIL_SIZE);
+        assert_eq!(thumbnail.thumbnail_height, PATIENT_PHOTO_THUMBNAIL_SIZE / 2);
+        assert!(thumbnail.content.starts_with(&[0xFF, 0xD8, 0xFF]));
+    }
+
+    #[test]
+    fn test_make_thumbnail_rejects_non_images() {
+        assert!(make_thumbnail(b"\x89PNG\r\n\x1a\nnot really a png").is_err());
+    }
+}
@@ -0,0 +1,616 @@
+/*!
+ * Patient Portal Service
+ *
+ * Sign-in, sessions and the data a patient sees in the patient portal.
+ *
+ * Sign-in: the patient gives the email address on their record and their
+ * date of birth. When both match an active record, a one-time link valid for
+ * 15 minutes is emailed to that address; opening it starts a 30-minute
+ * session. Callers never learn whether a link was sent.
+ *
+ * Everything done for a signed-in patient runs in a portal transaction
+ * (`begin_portal_transaction`), so RLS limits it to the patient's own
+ * record, appointments and signed documents whatever the query says.
+ */
+
+use std::path::PathBuf;
+
+use chrono::{Duration, NaiveDate, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::{
+    db::rls::{apply_portal_context, begin_portal_transaction},
+    models::{
+        patient_portal::{
+            generate_portal_login_token, hash_portal_login_token, PortalLoginCandidate,
+            PortalSession, MAX_PORTAL_LOGIN_LINKS, MAX_PORTAL_UPCOMING_APPOINTMENTS,
+            PORTAL_ENABLED_SETTING_KEY, PORTAL_LOGIN_LINK_MINUTES, PORTAL_SESSION_MINUTES,
+        },
+        Patient, PatientBlindIndexes, PatientDto, PortalAppointmentResponse,
+        PortalDocumentResponse, PortalProfileResponse, UpdatePortalContactRequest,
+    },
+    services::{
+        contact_propagation::{propagate_contact_change, ContactDetails, ContactPropagation},
+        email_service::EmailService,
+        BrandingService, SettingsService,
+    },
+    utils::{encryption::EncryptionKey, html::escape_html, AppError, Result},
+};
+
+/// Records that may use the portal (unqualified `patients` columns)
+const PORTAL_PATIENT_FILTER: &str =
+    "status = 'ACTIVE' AND anonymized_at IS NULL AND merged_into_id IS NULL";
+
+const SESSION_COLUMNS: &str = "id, patient_id, created_at, last_used_at, expires_at, revoked_at";
+
+/// Frontend route that handles sign-in links
+const PORTAL_LOGIN_PATH: &str = "/portal/sign-in";
+
+/// Service for the patient portal
+pub struct PatientPortalService {
+    pool: PgPool,
+    encryption_key: EncryptionKey,
+    storage_path: PathBuf,
+}
+
+impl PatientPortalService {
+    /// Create a new patient portal service
+    ///
+    /// `storage_path` is the generated document storage directory; downloads
+    /// outside it are refused.
+    pub fn new(pool: PgPool, encryption_key: EncryptionKey, storage_path: PathBuf) -> Self {
+        Self {
+            pool,
+            encryption_key,
+            storage_path,
+        }
+    }
+
+    /// Whether the practice has turned the portal on (`portal.enabled`)
+    pub async fn is_enabled(settings_service: &SettingsService) -> bool {
+        settings_service
+            .get_setting(PORTAL_ENABLED_SETTING_KEY)
+            .await
+            .ok()
+            .flatten()
+            .and_then(|s| s.setting_value.as_bool())
+            .unwrap_or(false)
+    }
+
+    // =========================================================================
+    // Sign-in
+    // =========================================================================
+
+    /// Email a sign-in link to every active patient with this email address
+    /// and date of birth
+    ///
+    /// Returns the number of links sent. At most `MAX_PORTAL_LOGIN_LINKS`
+    /// links go to one patient per link validity window.
+    pub async fn request_login_link(
+        &self,
+        email_service: Option<&EmailService>,
+        email: &str,
+        date_of_birth: NaiveDate,
+        ip_address: Option<&str>,
+    ) -> Result<usize> {
+        let Some(email_bidx) = PatientBlindIndexes::email_lookup(&self.encryption_key, email)
+        else {
+            return Ok(0);
+        };
+
+        let candidates = sqlx::query_as::<_, PortalLoginCandidate>(
+            "SELECT patient_id, first_name, date_of_birth, email FROM portal_login_candidates($1)",
+        )
+        .bind(email_bidx)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut matching = Vec::new();
+        for candidate in candidates {
+            let dob = self.decrypt(&candidate.date_of_birth)?;
+            if dob.parse::<NaiveDate>().ok() == Some(date_of_birth) {
+                matching.push(candidate);
+            }
+        }
+        if matching.is_empty() {
+            return Ok(0);
+        }
+
+        let Some(email_service) = email_service.filter(|e| e.is_enabled()) else {
+            tracing::warn!("Patient portal sign-in link not sent: email is not configured");
+            return Ok(0);
+        };
+
+        let base_url = BrandingService::new(self.pool.clone())
+            .load_settings()
+            .await
+            .ok()
+            .and_then(|s| s.public_base_url)
+            .unwrap_or_default();
+        let ip_address = ip_address.filter(|ip| ip.parse::<std::net::IpAddr>().is_ok());
+        let window_start = Utc::now() - Duration::minutes(PORTAL_LOGIN_LINK_MINUTES);
+
+        let mut sent = 0;
+        for candidate in matching {
+            let recent: i64 = sqlx::query_scalar(
+                "SELECT COUNT(*) FROM patient_portal_login_tokens WHERE patient_id = $1 AND created_at > $2",
+            )
+            .bind(candidate.patient_id)
+            .bind(window_start)
+            .fetch_one(&self.pool)
+            .await?;
+
+            if recent >= MAX_PORTAL_LOGIN_LINKS {
+                tracing::warn!(
+                    "Patient portal sign-in link for patient {} not sent: too many recent links",
+                    candidate.patient_id
+                );
+                continue;
+            }
+
+            let token = generate_portal_login_token();
+            let expires_at = Utc::now() + Duration::minutes(PORTAL_LOGIN_LINK_MINUTES);
+
+            sqlx::query(
+                r#"
+                INSERT INTO patient_portal_login_tokens (patient_id, token_hash, expires_at, ip_address)
+                VALUES ($1, $2, $3, $4::inet)
+                "#,
+            )
+            .bind(candidate.patient_id)
+            .bind(hash_portal_login_token(&token))
+            .bind(expires_at)
+            .bind(ip_address)
+            .execute(&self.pool)
+            .await?;
+
+            let first_name = self.decrypt(&candidate.first_name)?;
+            let to_email = self.decrypt(&candidate.email)?;
+            let url = portal_login_url(&base_url, &token);
+            let (subject, text, html) = portal_login_email(&first_name, &url);
+
+            match email_service
+                .send_notification(&to_email, &first_name, &subject, &text, Some(&html))
+                .await
+            {
+                Ok(result) if result.success => sent += 1,
+                Ok(result) => tracing::warn!(
+                    "Patient portal sign-in link for patient {} not sent: {}",
+                    candidate.patient_id,
+                    result.message
+                ),
+                Err(e) => tracing::error!(
+                    "Failed to send patient portal sign-in link for patient {}: {}",
+                    candidate.patient_id,
+                    e
+                ),
+            }
+        }
+
+        Ok(sent)
+    }
+
+    /// Use a sign-in link and start a portal session
+    ///
+    /// The link is locked while it is used, so it starts at most one
+    /// session. Unknown, used and expired links, and links of records that
+    /// can no longer use the portal, all fail the same way.
+    pub async fn start_session(
+        &self,
+        token: &str,
+        ip_address: Option<&str>,
+        user_agent: Option<&str>,
+    ) -> Result<(PortalSession, PortalProfileResponse)> {
+        let invalid = || AppError::Unauthorized("Sign-in link is invalid or has expired".to_string());
+
+        let mut tx = self.pool.begin().await?;
+
+        let link: Option<(Uuid, Uuid)> = sqlx::query_as(
+            r#"
+            SELECT id, patient_id FROM patient_portal_login_tokens
+            WHERE token_hash = $1 AND used_at IS NULL AND expires_at > NOW()
+            FOR UPDATE
+            "#,
+        )
+        .bind(hash_portal_login_token(token))
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some((link_id, patient_id)) = link else {
+            return Err(invalid());
+        };
+
+        sqlx::query("UPDATE patient_portal_login_tokens SET used_at = NOW() WHERE id = $1")
+            .bind(link_id)
+            .execute(&mut *tx)
+            .await?;
+
+        apply_portal_context(&mut tx, patient_id).await?;
+        let patient = self
+            .load_patient(&mut tx, patient_id)
+            .await?
+            .ok_or_else(invalid)?;
+
+        let ip_address = ip_address.filter(|ip| ip.parse::<std::net::IpAddr>().is_ok());
+        let session = sqlx::query_as::<_, PortalSession>(&format!(
+            r#"
+            INSERT INTO patient_portal_sessions (patient_id, login_token_id, ip_address, user_agent, expires_at)
+            VALUES ($1, $2, $3::inet, $4, $5)
+            RETURNING {}
+            "#,
+            SESSION_COLUMNS
+        ))
+        .bind(patient_id)
+        .bind(link_id)
+        .bind(ip_address)
+        .bind(user_agent)
+        .bind(Utc::now() + Duration::minutes(PORTAL_SESSION_MINUTES))
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok((session, PortalProfileResponse::from(patient)))
+    }
+
+    /// Check that a portal session is still active and mark it used
+    ///
+    /// Sessions end when they expire, when the patient signs out, and as soon
+    /// as the record is deactivated, merged or erased.
+    pub async fn authenticate(&self, patient_id: Uuid, session_id: Uuid) -> Result<bool> {
+        let mut tx = begin_portal_transaction(&self.pool, patient_id).await?;
+
+        let result = sqlx::query(&format!(
+            r#"
+            UPDATE patient_portal_sessions s SET last_used_at = NOW()
+            WHERE s.id = $1 AND s.patient_id = $2
+              AND s.revoked_at IS NULL AND s.expires_at > NOW()
+              AND EXISTS (SELECT 1 FROM patients p WHERE p.id = s.patient_id AND {})
+            "#,
+            PORTAL_PATIENT_FILTER
+        ))
+        .bind(session_id)
+        .bind(patient_id)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// End a portal session
+    pub async fn end_session(&self, session_id: Uuid) -> Result<()> {
+        sqlx::query(
+            "UPDATE patient_portal_sessions SET revoked_at = NOW() WHERE id = $1 AND revoked_at IS NULL",
+        )
+        .bind(session_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    // =========================================================================
+    // Portal data
+    // =========================================================================
+
+    /// The patient's own record
+    pub async fn profile(&self, patient_id: Uuid) -> Result<PortalProfileResponse> {
+        let mut tx = begin_portal_transaction(&self.pool, patient_id).await?;
+        let patient = self
+            .load_patient(&mut tx, patient_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Patient not found".to_string()))?;
+        tx.commit().await?;
+
+        Ok(PortalProfileResponse::from(patient))
+    }
+
+    /// Update the patient's contact details
+    ///
+    /// Only the contact fields and the blind indexes derived from them
+    /// change (the database refuses anything else from a portal session).
+    /// Queued notifications follow the new email and phone, in the same
+    /// transaction.
+    pub async fn update_contact(
+        &self,
+        patient_id: Uuid,
+        request: &UpdatePortalContactRequest,
+    ) -> Result<(PortalProfileResponse, ContactPropagation)> {
+        let key = &self.encryption_key;
+        let mut tx = begin_portal_transaction(&self.pool, patient_id).await?;
+
+        let existing = sqlx::query_as::<_, Patient>(&format!(
+            "SELECT * FROM patients WHERE id = $1 AND {} FOR UPDATE",
+            PORTAL_PATIENT_FILTER
+        ))
+        .bind(patient_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Patient not found".to_string()))?;
+        let old = existing.decrypt(key).map_err(internal)?;
+
+        let phone_primary = request
+            .phone_primary
+            .clone()
+            .or_else(|| old.phone_primary.clone());
+        let phone_secondary = request
+            .phone_secondary
+            .clone()
+            .or_else(|| old.phone_secondary.clone());
+        let email = request.email.clone().or_else(|| old.email.clone());
+        let blind_indexes = PatientBlindIndexes::compute(
+            key,
+            &old.first_name,
+            &old.last_name,
+            old.fiscal_code.as_deref(),
+            &[phone_primary.as_deref(), phone_secondary.as_deref()],
+            email.as_deref(),
+        );
+
+        let encrypted_phone_primary = match request.phone_primary {
+            Some(_) => key.encrypt_optional(&request.phone_primary).map_err(internal)?,
+            None => existing.phone_primary,
+        };
+        let encrypted_phone_secondary = match request.phone_secondary {
+            Some(_) => key
+                .encrypt_optional(&request.phone_secondary)
+                .map_err(internal)?,
+            None => existing.phone_secondary,
+        };
+        let encrypted_email = match request.email {
+            Some(_) => key.encrypt_optional(&request.email).map_err(internal)?,
+            None => existing.email,
+        };
+        let encrypted_address = match &request.address {
+            Some(address) => Some(sqlx::types::JsonValue::String(
+                key.encrypt_json(address).map_err(internal)?,
+            )),
+            None => existing.address,
+        };
+        let preferred_contact_method = request
+            .preferred_contact_method
+            .clone()
+            .unwrap_or(existing.preferred_contact_method);
+
+        sqlx::query(
+            r#"
+            UPDATE patients SET
+                phone_primary = $2, phone_secondary = $3, email = $4, address = $5,
+                preferred_contact_method = $6, phone_bidx = $7, email_bidx = $8,
+                search_trigram_bidx = $9, updated_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(patient_id)
+        .bind(encrypted_phone_primary)
+        .bind(encrypted_phone_secondary)
+        .bind(encrypted_email)
+        .bind(encrypted_address)
+        .bind(preferred_contact_method)
+        .bind(blind_indexes.phones)
+        .bind(blind_indexes.email)
+        .bind(blind_indexes.trigrams)
+        .execute(&mut *tx)
+        .await?;
+
+        let updated = self
+            .load_patient(&mut tx, patient_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Patient not found".to_string()))?;
+
+        let propagation = propagate_contact_change(
+            &mut tx,
+            patient_id,
+            &ContactDetails::from_patient(&old),
+            &ContactDetails::from_patient(&updated),
+        )
+        .await
+        .map_err(internal)?;
+
+        tx.commit().await?;
+
+        Ok((PortalProfileResponse::from(updated), propagation))
+    }
+
+    /// The patient's upcoming scheduled and confirmed appointments, soonest
+    /// first
+    pub async fn upcoming_appointments(
+        &self,
+        patient_id: Uuid,
+    ) -> Result<Vec<PortalAppointmentResponse>> {
+        let mut tx = begin_portal_transaction(&self.pool, patient_id).await?;
+
+        let appointments = sqlx::query_as::<_, PortalAppointmentResponse>(
+            r#"
+            SELECT a.id, a.scheduled_start, a.scheduled_end, a.duration_minutes,
+                   a.type::TEXT AS appointment_type, a.status::TEXT AS status,
+                   TRIM(CONCAT(u.first_name, ' ', u.last_name)) AS provider_name,
+                   a.confirmed_at
+            FROM appointments a
+            JOIN users u ON u.id = a.provider_id
+            WHERE a.patient_id = $1
+              AND a.scheduled_start >= NOW()
+              AND a.status IN ('PENDING', 'SCHEDULED', 'CONFIRMED')
+            ORDER BY a.scheduled_start
+            LIMIT $2
+            "#,
+        )
+        .bind(patient_id)
+        .bind(MAX_PORTAL_UPCOMING_APPOINTMENTS)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(appointments)
+    }
+
+    /// The patient's signed documents, most recently signed first
+    pub async fn documents(&self, patient_id: Uuid) -> Result<Vec<PortalDocumentResponse>> {
+        let mut tx = begin_portal_transaction(&self.pool, patient_id).await?;
+
+        let documents = sqlx::query_as::<_, PortalDocumentResponse>(
+            r#"
+            SELECT id, document_type, document_title, document_filename, file_size_bytes,
+                   visit_date, signed_at, created_at
+            FROM generated_documents
+            WHERE patient_id = $1 AND is_signed = true AND status IN ('GENERATED', 'DELIVERED')
+            ORDER BY COALESCE(signed_at, created_at) DESC
+            "#,
+        )
+        .bind(patient_id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(documents)
+    }
+
+    /// File path and download filename of one of the patient's signed
+    /// documents
+    pub async fn document_file(
+        &self,
+        patient_id: Uuid,
+        document_id: Uuid,
+    ) -> Result<(PathBuf, String)> {
+        let mut tx = begin_portal_transaction(&self.pool, patient_id).await?;
+
+        let document: Option<(String, String)> = sqlx::query_as(
+            r#"
+            SELECT file_path, document_filename FROM generated_documents
+            WHERE id = $1 AND patient_id = $2
+              AND is_signed = true AND status IN ('GENERATED', 'DELIVERED')
+            "#,
+        )
+        .bind(document_id)
+        .bind(patient_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        let (file_path, filename) =
+            document.ok_or_else(|| AppError::NotFound("Document not found".to_string()))?;
+
+        // Validate path is within storage directory to prevent path traversal
+        let canonical_file = std::path::Path::new(&file_path).canonicalize().map_err(|e| {
+            tracing::error!("Failed to resolve document file {}: {}", document_id, e);
+            AppError::NotFound("Document file not found".to_string())
+        })?;
+        let canonical_storage = self.storage_path.canonicalize().map_err(|e| {
+            AppError::Internal(format!("Failed to resolve storage path: {}", e))
+        })?;
+        if !canonical_file.starts_with(&canonical_storage) {
+            tracing::error!(
+                "Document {} file path is outside the storage directory",
+                document_id
+            );
+            return Err(AppError::NotFound("Document file not found".to_string()));
+        }
+
+        Ok((canonical_file, filename))
+    }
+
+    // =========================================================================
+    // Helpers
+    // =========================================================================
+
+    /// Load and decrypt the patient, if the record may use the portal
+    async fn load_patient(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        patient_id: Uuid,
+    ) -> Result<Option<PatientDto>> {
+        let patient = sqlx::query_as::<_, Patient>(&format!(
+            "SELECT * FROM patients WHERE id = $1 AND {}",
+            PORTAL_PATIENT_FILTER
+        ))
+        .bind(patient_id)
+        .fetch_optional(&mut **tx)
+        .await?;
+
+        patient
+            .map(|p| p.decrypt(&self.encryption_key).map_err(internal))
+            .transpose()
+    }
+
+    fn decrypt(&self, value: &str) -> Result<String> {
+        self.encryption_key
+            .decrypt(value)
+            .map_err(|e| AppError::Internal(format!("Failed to decrypt patient data: {}", e)))
+    }
+}
+
+fn internal(e: anyhow::Error) -> AppError {
+    AppError::Internal(e.to_string())
+}
+
+/// Build the sign-in link for a token
+fn portal_login_url(base_url: &str, token: &str) -> String {
+    format!(
+        "{}{}?token={}",
+        base_url.trim().trim_end_matches('/'),
+        PORTAL_LOGIN_PATH,
+        token
+    )
+}
+
+/// Build the sign-in email (subject, plain text, HTML)
+fn portal_login_email(first_name: &str, url: &str) -> (String, String, String) {
+    let subject = "Your patient portal sign-in link".to_string();
+
+    let text = format!(
+        "Dear {first_name},\n\n\
+         Open the link below to sign in to the patient portal:\n\n\
+         {url}\n\n\
+         The link can be used once and expires in {minutes} minutes. If you did not ask \
+         to sign in, you can ignore this email.\n\n\
+         Best regards,\nDocPat Medical Practice",
+        minutes = PORTAL_LOGIN_LINK_MINUTES,
+    );
+
+    let html = format!(
+        r#"<!DOCTYPE html>
+<html>
+<head><meta charset="utf-8"></head>
+<body style="font-family: Arial, sans-serif; line-height: 1.6; color: #333;">
+<div style="max-width: 600px; margin: 0 auto; padding: 20px;">
+<p>Dear {first_name},</p>
+<p>Open the link below to sign in to the patient portal:</p>
+<p><a href="{url}">Sign in</a></p>
+<p style="font-size: 12px; color: #666;">The link can be used once and expires in {minutes} minutes. If you did not ask to sign in, you can ignore this email.</p>
+</div>
+</body>
+</html>"#,
+        first_name = escape_html(first_name),
+        url = escape_html(url),
+        minutes = PORTAL_LOGIN_LINK_MINUTES,
+    );
+
+    (subject, text, html)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_portal_login_url() {
+        assert_eq!(
+            portal_login_url("https://studio.example.com/", "abc"),
+            "https://studio.example.com/portal/sign-in?token=abc"
+        );
+        assert_eq!(portal_login_url("", "abc"), "/portal/sign-in?token=abc");
+    }
+
+    #[test]
+    fn test_portal_login_email_escapes_name() {
+        let (_, text, html) = portal_login_email("<b>Anna</b>", "/portal/sign-in?token=abc");
+        assert!(text.contains("Dear <b>Anna</b>"));
+        assert!(html.contains("&lt;b&gt;Anna&lt;/b&gt;"));
+        assert!(html.contains("/portal/sign-in?token=abc"));
+    }
+}
@@ -0,0 +1,577 @@
+/*!
+ * Patient Problem Service
+ *
+ * Longitudinal problem list, under RLS (doctors edit, nurses read). A
+ * patient has at most one ACTIVE entry per ICD-10 code: promoting a visit
+ * diagnosis whose condition is already on the list links the visit to the
+ * existing problem instead of adding a second entry.
+ *
+ * Problems are medical records: deleting one only marks it deleted.
+ */
+
+use crate::{
+    db::rls::begin_with_rls,
+    models::{
+        visit_diagnosis::validate_icd10_code, CreatePatientProblemRequest, LinkedVisit,
+        ListPatientProblemsQuery, PatientProblem, PatientProblemResponse, ProblemStatus,
+        UpdatePatientProblemRequest,
+    },
+    utils::{encryption::EncryptionKey, AppError, Result},
+};
+use chrono::NaiveDate;
+use sqlx::{PgConnection, PgPool};
+use tracing::info;
+use uuid::Uuid;
+
+const PROBLEM_SELECT: &str = r#"
+    SELECT id, patient_id, icd10_code, icd10_description, status, onset_date, resolved_date,
+           clinical_notes, source_diagnosis_id, created_by, created_at, updated_by, updated_at
+    FROM patient_problems
+    WHERE deleted_at IS NULL
+"#;
+
+const PROBLEM_RETURNING: &str = r#"
+    RETURNING id, patient_id, icd10_code, icd10_description, status, onset_date, resolved_date,
+              clinical_notes, source_diagnosis_id, created_by, created_at, updated_by, updated_at
+"#;
+
+/// Outcome of promoting a visit diagnosis
+pub struct PromotedProblem {
+    pub problem: PatientProblemResponse,
+    /// False when the condition was already on the list
+    pub created: bool,
+}
+
+/// Patient problem service
+pub struct PatientProblemService {
+    pool: PgPool,
+    encryption_key: EncryptionKey,
+}
+
+impl PatientProblemService {
+    /// Create a new patient problem service
+    pub fn new(pool: PgPool, encryption_key: EncryptionKey) -> Self {
+        Self {
+            pool,
+            encryption_key,
+        }
+    }
+
+    /// Add a problem to the list
+    ///
+    /// Fails with Validation for a malformed ICD-10 code or a visit that is
+    /// not the patient's, and with Conflict if the condition is already an
+    /// active problem or the patient is anonymized or merged.
+    pub async fn create_problem(
+        &self,
+        patient_id: Uuid,
+        req: &CreatePatientProblemRequest,
+        user_id: Uuid,
+    ) -> Result<PatientProblemResponse> {
+        let code = req.icd10_code.trim().to_uppercase();
+        validate_icd10_code(&code).map_err(|e| AppError::Validation(e.to_string()))?;
+
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        self.check_patient(&mut tx, patient_id).await?;
+
+        let visit_date = match req.visit_id {
+            Some(visit_id) => Some(self.visit_date(&mut tx, patient_id, visit_id).await?),
+            None => None,
+        };
+
+        let notes = self.encrypt_optional(&req.clinical_notes)?;
+        let inserted = sqlx::query_as::<_, PatientProblem>(&format!(
+            r#"
+            INSERT INTO patient_problems (
+                patient_id, icd10_code, icd10_description, onset_date, clinical_notes, created_by
+            )
+            VALUES ($1, $2, $3, $4, $5, $6)
+            {}
+            "#,
+            PROBLEM_RETURNING
+        ))
+        .bind(patient_id)
+        .bind(&code)
+        .bind(req.icd10_description.trim())
+        .bind(req.onset_date)
+        .bind(notes)
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await;
+
+        let problem = match inserted {
+            Ok(problem) => problem,
+            Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+                return Err(AppError::Conflict(format!(
+                    "{} is already an active problem of this patient",
+                    code
+                )));
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        if let (Some(visit_id), Some(visit_date)) = (req.visit_id, visit_date) {
+            self.link(&mut tx, problem.id, visit_id, visit_date, user_id)
+                .await?;
+        }
+
+        let response = self.load_response(&mut tx, problem).await?;
+        tx.commit().await?;
+
+        info!(
+            "Problem {} ({}) added for patient {} by {}",
+            response.id, code, patient_id, user_id
+        );
+
+        Ok(response)
+    }
+
+    /// Promote a visit diagnosis onto the patient's problem list
+    ///
+    /// The problem takes the diagnosis's code, description and notes, with
+    /// the visit date as onset, and is linked to the visit. If the condition
+    /// is already an active problem, the visit is linked to it instead.
+    /// Differential and rule-out diagnoses cannot be promoted.
+    pub async fn promote_diagnosis(
+        &self,
+        diagnosis_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<PromotedProblem> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        let diagnosis: Option<(
+            Uuid,
+            NaiveDate,
+            Uuid,
+            String,
+            String,
+            Option<String>,
+            Option<String>,
+        )> = sqlx::query_as(
+            r#"
+            SELECT visit_id, visit_date, patient_id, icd10_code, icd10_description,
+                   diagnosis_type, clinical_notes
+            FROM visit_diagnoses
+            WHERE id = $1
+            "#,
+        )
+        .bind(diagnosis_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some((visit_id, visit_date, patient_id, code, description, diagnosis_type, notes)) =
+            diagnosis
+        else {
+            return Err(AppError::NotFound(format!(
+                "Diagnosis {} not found",
+                diagnosis_id
+            )));
+        };
+
+        if matches!(diagnosis_type.as_deref(), Some("DIFFERENTIAL" | "RULE_OUT")) {
+            return Err(AppError::Validation(
+                "Only provisional or confirmed diagnoses can be added to the problem list"
+                    .to_string(),
+            ));
+        }
+
+        self.check_patient(&mut tx, patient_id).await?;
+
+        let existing = sqlx::query_as::<_, PatientProblem>(&format!(
+            "{} AND patient_id = $1 AND icd10_code = $2 AND status = $3 FOR UPDATE",
+            PROBLEM_SELECT
+        ))
+        .bind(patient_id)
+        .bind(&code)
+        .bind(ProblemStatus::Active.as_str())
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let (problem, created) = match existing {
+            Some(problem) => (problem, false),
+            None => {
+                // The notes stay encrypted with the same key
+                let problem = sqlx::query_as::<_, PatientProblem>(&format!(
+                    r#"
+                    INSERT INTO patient_problems (
+                        patient_id, icd10_code, icd10_description, onset_date, clinical_notes,
+                        source_diagnosis_id, created_by
+                    )
+                    VALUES ($1, $2, $3, $4, $5, $6, $7)
+                    {}
+                    "#,
+                    PROBLEM_RETURNING
+                ))
+                .bind(patient_id)
+                .bind(&code)
+                .bind(&description)
+                .bind(visit_date)
+                .bind(notes)
+                .bind(diagnosis_id)
+                .bind(user_id)
+                .fetch_one(&mut *tx)
+                .await?;
+                (problem, true)
+            }
+        };
+
+        self.link(&mut tx, problem.id, visit_id, visit_date, user_id)
+            .await?;
+
+        let response = self.load_response(&mut tx, problem).await?;
+        tx.commit().await?;
+
+        info!(
+            "Diagnosis {} promoted to problem {} of patient {} by {}",
+            diagnosis_id, response.id, patient_id, user_id
+        );
+
+        Ok(PromotedProblem {
+            problem: response,
+            created,
+        })
+    }
+
+    /// List a patient's problems, active first, then by onset (newest first)
+    pub async fn list_problems(
+        &self,
+        patient_id: Uuid,
+        query: &ListPatientProblemsQuery,
+        user_id: Uuid,
+    ) -> Result<Vec<PatientProblemResponse>> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        let problems = sqlx::query_as::<_, PatientProblem>(&format!(
+            r#"
+            {}
+              AND patient_id = $1
+              AND ($2::TEXT IS NULL OR status = $2)
+            ORDER BY status = 'ACTIVE' DESC, onset_date DESC NULLS LAST, created_at DESC
+            "#,
+            PROBLEM_SELECT
+        ))
+        .bind(patient_id)
+        .bind(query.status.map(|s| s.as_str()))
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let ids: Vec<Uuid> = problems.iter().map(|p| p.id).collect();
+        let links = self.linked_visits(&mut tx, &ids).await?;
+        tx.commit().await?;
+
+        problems
+            .iter()
+            .map(|problem| {
+                let visits = links
+                    .iter()
+                    .filter(|link| link.problem_id == problem.id)
+                    .cloned()
+                    .collect();
+                self.decrypt(problem, visits)
+            })
+            .collect()
+    }
+
+    /// Get a problem with its linked visits
+    pub async fn get_problem(
+        &self,
+        patient_id: Uuid,
+        problem_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<PatientProblemResponse> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        let problem = self.find(&mut tx, patient_id, problem_id).await?;
+        let response = self.load_response(&mut tx, problem).await?;
+        tx.commit().await?;
+
+        Ok(response)
+    }
+
+    /// Update a problem
+    ///
+    /// Fails with Conflict when reactivating a problem whose condition is
+    /// already active in another entry.
+    pub async fn update_problem(
+        &self,
+        patient_id: Uuid,
+        problem_id: Uuid,
+        req: &UpdatePatientProblemRequest,
+        user_id: Uuid,
+    ) -> Result<PatientProblemResponse> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        let notes = self.encrypt_optional(&req.clinical_notes)?;
+        let updated = sqlx::query(
+            r#"
+            UPDATE patient_problems
+            SET icd10_description = COALESCE($3, icd10_description),
+                status = COALESCE($4, status),
+                onset_date = COALESCE($5, onset_date),
+                resolved_date = CASE COALESCE($4, status)
+                    WHEN 'ACTIVE' THEN NULL
+                    ELSE COALESCE($6, resolved_date, CURRENT_DATE)
+                END,
+                clinical_notes = COALESCE($7, clinical_notes),
+                updated_by = $8
+            WHERE id = $1 AND patient_id = $2 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(problem_id)
+        .bind(patient_id)
+        .bind(req.icd10_description.as_deref().map(str::trim))
+        .bind(req.status.map(|s| s.as_str()))
+        .bind(req.onset_date)
+        .bind(req.resolved_date)
+        .bind(notes)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await;
+
+        let updated = match updated {
+            Ok(result) => result.rows_affected(),
+            Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+                return Err(AppError::Conflict(
+                    "This condition is already an active problem of this patient".to_string(),
+                ));
+            }
+            Err(sqlx::Error::Database(e)) if e.is_check_violation() => {
+                return Err(AppError::Validation(
+                    "resolved_date cannot be before onset_date".to_string(),
+                ));
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        if updated == 0 {
+            return Err(AppError::NotFound(format!(
+                "Problem {} not found",
+                problem_id
+            )));
+        }
+
+        let problem = self.find(&mut tx, patient_id, problem_id).await?;
+        let response = self.load_response(&mut tx, problem).await?;
+        tx.commit().await?;
+
+        Ok(response)
+    }
+
+    /// Mark a problem deleted
+    pub async fn delete_problem(
+        &self,
+        patient_id: Uuid,
+        problem_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<()> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        let deleted = sqlx::query(
+            r#"
+            UPDATE patient_problems
+            SET deleted_at = NOW(), deleted_by = $3, updated_by = $3
+            WHERE id = $1 AND patient_id = $2 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(problem_id)
+        .bind(patient_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        if deleted == 0 {
+            return Err(AppError::NotFound(format!(
+                "Problem {} not found",
+                problem_id
+            )));
+        }
+
+        tx.commit().await?;
+
+        info!(
+            "Problem {} of patient {} deleted by {}",
+            problem_id, patient_id, user_id
+        );
+
+        Ok(())
+    }
+
+    /// Link a visit of the patient to a problem (no-op if already linked)
+    pub async fn link_visit(
+        &self,
+        patient_id: Uuid,
+        problem_id: Uuid,
+        visit_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<PatientProblemResponse> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        let problem = self.find(&mut tx, patient_id, problem_id).await?;
+        let visit_date = self.visit_date(&mut tx, patient_id, visit_id).await?;
+        self.link(&mut tx, problem_id, visit_id, visit_date, user_id)
+            .await?;
+
+        let response = self.load_response(&mut tx, problem).await?;
+        tx.commit().await?;
+
+        Ok(response)
+    }
+
+    /// Unlink a visit from a problem
+    pub async fn unlink_visit(
+        &self,
+        patient_id: Uuid,
+        problem_id: Uuid,
+        visit_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<()> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        self.find(&mut tx, patient_id, problem_id).await?;
+        let removed = sqlx::query(
+            "DELETE FROM patient_problem_visits WHERE problem_id = $1 AND visit_id = $2",
+        )
+        .bind(problem_id)
+        .bind(visit_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        if removed == 0 {
+            return Err(AppError::NotFound(format!(
+                "Visit {} is not linked to problem {}",
+                visit_id, problem_id
+            )));
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn find(
+        &self,
+        tx: &mut PgConnection,
+        patient_id: Uuid,
+        problem_id: Uuid,
+    ) -> Result<PatientProblem> {
+        sqlx::query_as::<_, PatientProblem>(&format!(
+            "{} AND id = $1 AND patient_id = $2",
+            PROBLEM_SELECT
+        ))
+        .bind(problem_id)
+        .bind(patient_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Problem {} not found", problem_id)))
+    }
+
+    /// The patient must exist and be neither anonymized nor merged
+    async fn check_patient(&self, tx: &mut PgConnection, patient_id: Uuid) -> Result<()> {
+        let patient: Option<(
+            Option<chrono::DateTime<chrono::Utc>>,
+            Option<chrono::DateTime<chrono::Utc>>,
+        )> = sqlx::query_as("SELECT anonymized_at, merged_at FROM patients WHERE id = $1")
+            .bind(patient_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        match patient {
+            None => Err(AppError::NotFound(format!(
+                "Patient {} not found",
+                patient_id
+            ))),
+            Some((Some(_), _)) => Err(AppError::Conflict(format!(
+                "Patient {} has been anonymized",
+                patient_id
+            ))),
+            Some((_, Some(_))) => Err(AppError::Conflict(format!(
+                "Patient {} has been merged into another record",
+                patient_id
+            ))),
+            Some(_) => Ok(()),
+        }
+    }
+
+    /// Date of a visit of the patient (the visits partition key)
+    async fn visit_date(
+        &self,
+        tx: &mut PgConnection,
+        patient_id: Uuid,
+        visit_id: Uuid,
+    ) -> Result<NaiveDate> {
+        sqlx::query_scalar("SELECT visit_date FROM visits WHERE id = $1 AND patient_id = $2")
+            .bind(visit_id)
+            .bind(patient_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| {
+                AppError::Validation(format!("Visit {} is not a visit of this patient", visit_id))
+            })
+    }
+
+    async fn link(
+        &self,
+        tx: &mut PgConnection,
+        problem_id: Uuid,
+        visit_id: Uuid,
+        visit_date: NaiveDate,
+        user_id: Uuid,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO patient_problem_visits (problem_id, visit_id, visit_date, linked_by)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (problem_id, visit_id) DO NOTHING
+            "#,
+        )
+        .bind(problem_id)
+        .bind(visit_id)
+        .bind(visit_date)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+        Ok(())
+    }
+
+    async fn linked_visits(
+        &self,
+        tx: &mut PgConnection,
+        problem_ids: &[Uuid],
+    ) -> Result<Vec<LinkedVisit>> {
+        Ok(sqlx::query_as::<_, LinkedVisit>(
+            r#"
+            SELECT problem_id, visit_id, visit_date, linked_at
+            FROM patient_problem_visits
+            WHERE problem_id = ANY($1)
+            ORDER BY visit_date DESC
+            "#,
+        )
+        .bind(problem_ids)
+        .fetch_all(&mut *tx)
+        .await?)
+    }
+
+    async fn load_response(
+        &self,
+        tx: &mut PgConnection,
+        problem: PatientProblem,
+    ) -> Result<PatientProblemResponse> {
+        let visits = self.linked_visits(tx, &[problem.id]).await?;
+        self.decrypt(&problem, visits)
+    }
+
+    fn decrypt(
+        &self,
+        problem: &PatientProblem,
+        visits: Vec<LinkedVisit>,
+    ) -> Result<PatientProblemResponse> {
+        problem
+            .decrypt(&self.encryption_key, visits)
+            .map_err(|e| AppError::Internal(format!("Failed to decrypt problem: {}", e)))
+    }
+
+    fn encrypt_optional(&self, value: &Option<String>) -> Result<Option<String>> {
+        self.encryption_key
+            .encrypt_optional(value)
+            .map_err(|e| AppError::Internal(format!("Failed to encrypt problem notes: {}", e)))
+    }
+}
@@ -1,17 +1,50 @@
 // Patient Service Layer
 // Business logic for patient management, duplicate detection, and search
 
+use crate::db::rls::{apply_rls_context, include_trashed_records, SYSTEM_ROLE, SYSTEM_USER_ID};
 use crate::models::{
-    AuditAction, AuditLog, CreateAuditLog, CreatePatientRequest, EntityType, Patient, PatientDto,
-    PatientSearchFilter, RequestContext, UpdatePatientRequest,
+    patient::{
+        autocomplete_terms, matches_autocomplete, normalize_fiscal_code, normalize_last_name,
+        AUTOCOMPLETE_MAX_RESULTS, LAST_NAME_PREFIX_MAX_CHARS, LAST_NAME_PREFIX_MIN_CHARS,
+    },
+    AuditAction, AuditLog, CreateAuditLog, CreatePatientRequest, EntityType, Patient, PatientAutocompleteQuery,
+    PatientBlindIndexes, PatientDto, PatientSearchFilter, RequestContext, UpdatePatientRequest,
 };
 use crate::utils::encryption::EncryptionKey;
 use anyhow::{Context, Result};
 use chrono::Utc;
 use sqlx::PgPool;
+use tokio::time::{sleep, Duration};
+use tracing::{error, info, warn};
 use uuid::Uuid;
 use validator::Validate;
 
+/// Pause between runs of the blind index backfill
+const BACKFILL_INTERVAL_SECS: u64 = 300;
+
+/// Patients indexed per backfill run
+const BACKFILL_BATCH_SIZE: i64 = 200;
+
+/// Spawn the background job computing the blind indexes of patients saved
+/// before they existed
+pub fn spawn_patient_blind_index_backfill(pool: PgPool, encryption_key: EncryptionKey) {
+    let service = PatientService::new(pool, encryption_key);
+
+    tokio::spawn(async move {
+        loop {
+            match service.backfill_blind_indexes().await {
+                Ok(indexed) if indexed as i64 == BACKFILL_BATCH_SIZE => continue,
+                Ok(0) => {}
+                Ok(indexed) => info!("Computed blind indexes of {} patients", indexed),
+                Err(e) => error!("Patient blind index backfill failed: {:#}", e),
+            }
+            sleep(Duration::from_secs(BACKFILL_INTERVAL_SECS)).await;
+        }
+    });
+
+    info!("Patient blind index backfill spawned as background task");
+}
+
 /// Possible duplicate patient match
 #[derive(Debug, Clone)]
 pub struct PotentialDuplicate {
@@ -150,7 +183,7 @@ impl PatientService {
         user_id: Uuid,
         request_ctx: Option<&RequestContext>,
     ) -> Result<()> {
-        Patient::soft_delete(&self.pool, id)
+        Patient::soft_delete(&self.pool, id, user_id)
             .await
             .context("Failed to delete patient")?;
 
@@ -217,6 +250,46 @@ impl PatientService {
             param_index += 1;
         }
 
+        // Exact and prefix lookups on encrypted fields go through their blind
+        // indexes; a value that normalizes to nothing matches no patient
+        let key = &self.encryption_key;
+        let fiscal_code_bidx = filter.fiscal_code.as_deref().map(|value| {
+            PatientBlindIndexes::fiscal_code_lookup(key, value).unwrap_or_default()
+        });
+        let phone_bidx = filter
+            .phone
+            .as_deref()
+            .map(|value| PatientBlindIndexes::phone_lookup(key, value).unwrap_or_default());
+        let email_bidx = filter
+            .email
+            .as_deref()
+            .map(|value| PatientBlindIndexes::email_lookup(key, value).unwrap_or_default());
+        let last_name_prefix = filter.last_name.as_deref().map(normalize_last_name);
+        let last_name_prefix_bidx = filter
+            .last_name
+            .as_deref()
+            .and_then(|value| PatientBlindIndexes::last_name_prefix_lookup(key, value));
+
+        if fiscal_code_bidx.is_some() {
+            conditions.push(format!("fiscal_code_bidx = ${}", param_index));
+            param_index += 1;
+        }
+
+        if last_name_prefix_bidx.is_some() {
+            conditions.push(format!("last_name_prefix_bidx @> ARRAY[${}]::TEXT[]", param_index));
+            param_index += 1;
+        }
+
+        if phone_bidx.is_some() {
+            conditions.push(format!("phone_bidx @> ARRAY[${}]::TEXT[]", param_index));
+            param_index += 1;
+        }
+
+        if email_bidx.is_some() {
+            conditions.push(format!("email_bidx = ${}", param_index));
+            param_index += 1;
+        }
+
         // Age filters (calculated from date_of_birth)
         if filter.min_age.is_some() || filter.max_age.is_some() {
             // Note: Since date_of_birth is encrypted, we need to decrypt first
@@ -257,8 +330,13 @@ impl PatientService {
 
         // When there's a text query, we need to fetch ALL records first because
         // the text search happens in-memory (names are encrypted).
-        // We'll apply limit AFTER the in-memory filtering.
-        let has_text_query = filter.query.is_some();
+        // We'll apply limit AFTER the in-memory filtering. Last name prefixes
+        // too short or too long for the index are checked in-memory as well.
+        let last_name_in_memory = last_name_prefix.as_ref().is_some_and(|prefix| {
+            !(LAST_NAME_PREFIX_MIN_CHARS..=LAST_NAME_PREFIX_MAX_CHARS)
+                .contains(&prefix.chars().count())
+        });
+        let has_text_query = filter.query.is_some() || last_name_in_memory;
         // Clamp page size to prevent uncontrolled allocation (CWE-770)
         let requested_limit = filter.limit.unwrap_or(50).min(100);
         let offset = filter.offset.unwrap_or(0);
@@ -288,6 +366,13 @@ impl PatientService {
             query = query.bind(gender);
         }
 
+        for bidx in [fiscal_code_bidx, last_name_prefix_bidx, phone_bidx, email_bidx]
+            .into_iter()
+            .flatten()
+        {
+            query = query.bind(bidx);
+        }
+
         let patients = query
             .fetch_all(executor)
             .await
@@ -332,6 +417,10 @@ impl PatientService {
             });
         }
 
+        if let Some(ref prefix) = last_name_prefix {
+            decrypted.retain(|p| normalize_last_name(&p.last_name).starts_with(prefix.as_str()));
+        }
+
         // Apply age filters (in-memory since DOB is encrypted)
         if let Some(min_age) = filter.min_age {
             let max_dob = Utc::now().date_naive() - chrono::Duration::days(min_age as i64 * 365);
@@ -357,10 +446,115 @@ impl PatientService {
         Ok(decrypted)
     }
 
+    /// Autocomplete patients by part of their name, fiscal code or phone
+    ///
+    /// Candidates are found through the trigram blind index (the query's
+    /// trigrams must all be among the patient's), patients whose last name
+    /// starts with the first word coming first. Candidates are decrypted
+    /// and checked, since trigrams do not keep their order, then sorted by
+    /// name. Patients not indexed yet are not found.
+    ///
+    /// IMPORTANT: This method requires RLS context to be set if RLS is enabled on the patients table.
+    pub async fn autocomplete_patients<'e, E>(
+        &self,
+        executor: E,
+        query: PatientAutocompleteQuery,
+        user_id: Option<Uuid>,
+        request_ctx: Option<&RequestContext>,
+    ) -> Result<Vec<PatientDto>>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+    {
+        let terms = autocomplete_terms(&query.q);
+        let Some(first_term) = terms.first() else {
+            return Ok(Vec::new());
+        };
+        let limit = query
+            .limit
+            .unwrap_or(AUTOCOMPLETE_MAX_RESULTS)
+            .clamp(1, AUTOCOMPLETE_MAX_RESULTS);
+
+        let key = &self.encryption_key;
+        let trigrams = PatientBlindIndexes::autocomplete_lookup(key, &query.q);
+        let last_name_prefix_bidx = PatientBlindIndexes::last_name_prefix_lookup(key, first_term);
+
+        // Terms of two characters have no trigram: without longer ones, only
+        // last names can be looked up (by prefix)
+        let (condition, lookup) = if !trigrams.is_empty() {
+            ("search_trigram_bidx @> $1::TEXT[]", trigrams)
+        } else if let Some(prefix) = &last_name_prefix_bidx {
+            ("last_name_prefix_bidx @> $1::TEXT[]", vec![prefix.clone()])
+        } else {
+            return Ok(Vec::new());
+        };
+        let status_condition = if query.status.is_some() { " AND status = $4" } else { "" };
+
+        // A few more candidates than needed, for those failing the check
+        let query_str = format!(
+            r#"
+            SELECT * FROM patients
+            WHERE {}{}
+            ORDER BY last_name_prefix_bidx @> ARRAY[$2]::TEXT[] DESC, created_at DESC
+            LIMIT $3
+            "#,
+            condition, status_condition
+        );
+
+        let mut sql = sqlx::query_as::<_, Patient>(&query_str)
+            .bind(lookup)
+            .bind(&last_name_prefix_bidx)
+            .bind(limit * 3);
+        if let Some(status) = &query.status {
+            sql = sql.bind(status);
+        }
+        let patients = sql
+            .fetch_all(executor)
+            .await
+            .context("Failed to autocomplete patients")?;
+
+        let mut matches: Vec<PatientDto> = patients
+            .into_iter()
+            .map(|p| p.decrypt(&self.encryption_key))
+            .collect::<Result<Vec<_>>>()?;
+        matches.retain(|p| matches_autocomplete(p, &terms));
+        matches.sort_by_cached_key(|p| {
+            let last_name = normalize_last_name(&p.last_name);
+            (
+                !last_name.starts_with(first_term.as_str()),
+                last_name,
+                normalize_last_name(&p.first_name),
+            )
+        });
+        matches.truncate(limit as usize);
+
+        if !matches.is_empty() {
+            let _ = AuditLog::create(
+                &self.pool,
+                CreateAuditLog {
+                    user_id,
+                    action: AuditAction::Search,
+                    entity_type: EntityType::Patient,
+                    entity_id: None,
+                    changes: Some(serde_json::json!({
+                        "search_query": query.q,
+                        "results_count": matches.len()
+                    })),
+                    ip_address: request_ctx.and_then(|c| c.ip_address.clone()),
+                    user_agent: request_ctx.and_then(|c| c.user_agent.clone()),
+                    request_id: request_ctx.map(|c| c.request_id),
+                },
+            )
+            .await;
+        }
+
+        Ok(matches)
+    }
+
     /// Find potential duplicate patients
-    /// Note: Due to non-deterministic AES-GCM encryption, we must fetch and decrypt
-    /// all patients to check for duplicates. This is the correct approach for security
-    /// but may be slower for large datasets. Consider using indexed hash columns for production.
+    /// Records already merged into another patient are skipped.
+    /// Candidates are found through the fiscal code and last name blind
+    /// indexes, then decrypted to compare; patients not indexed yet are
+    /// always decrypted.
     ///
     /// IMPORTANT: This method requires RLS context to be set if RLS is enabled on the patients table.
     /// Pass a connection with RLS context (e.g., from within a transaction with SET LOCAL).
@@ -372,24 +566,29 @@ impl PatientService {
         let mut duplicates = Vec::new();
 
         // Check for exact fiscal code match (highest confidence)
-        // Fetch all patients with fiscal codes and decrypt to compare
         if let Some(ref fiscal_code) = data.fiscal_code {
-            tracing::debug!("Checking for duplicate fiscal code: {}", fiscal_code);
-
-            let all_patients = sqlx::query_as::<_, Patient>(
-                "SELECT * FROM patients WHERE fiscal_code IS NOT NULL"
+            tracing::debug!("Checking for duplicate fiscal code");
+
+            let fiscal_code_bidx = PatientBlindIndexes::fiscal_code_lookup(&self.encryption_key, fiscal_code);
+            let candidates = sqlx::query_as::<_, Patient>(
+                r#"
+                SELECT * FROM patients
+                WHERE fiscal_code IS NOT NULL AND merged_at IS NULL
+                  AND (fiscal_code_bidx = $1 OR last_name_bidx IS NULL)
+                "#,
             )
+            .bind(fiscal_code_bidx)
             .fetch_all(&mut *conn)
             .await?;
 
-            tracing::debug!("Found {} patients with fiscal codes to check", all_patients.len());
+            tracing::debug!("Found {} patients with fiscal codes to check", candidates.len());
 
-            for patient in all_patients {
+            let normalized = normalize_fiscal_code(fiscal_code);
+            for patient in candidates {
                 // Decrypt and compare fiscal code
                 if let Some(ref encrypted_fc) = patient.fiscal_code {
                     if let Ok(decrypted_fc) = self.encryption_key.decrypt(encrypted_fc) {
-                        tracing::debug!("Comparing '{}' with '{}'", decrypted_fc, fiscal_code);
-                        if &decrypted_fc == fiscal_code {
+                        if normalize_fiscal_code(&decrypted_fc) == normalized {
                             tracing::warn!("Found duplicate fiscal code for patient {}", patient.medical_record_number);
                             duplicates.push(PotentialDuplicate {
                                 patient_id: patient.id,
@@ -404,13 +603,15 @@ impl PatientService {
         }
 
         // Check for same first name, last name, and date of birth (medium confidence)
-        let all_patients = sqlx::query_as::<_, Patient>(
-            "SELECT * FROM patients"
+        let last_name_bidx = PatientBlindIndexes::last_name_lookup(&self.encryption_key, &data.last_name);
+        let candidates = sqlx::query_as::<_, Patient>(
+            "SELECT * FROM patients WHERE merged_at IS NULL AND (last_name_bidx = $1 OR last_name_bidx IS NULL)"
         )
+        .bind(last_name_bidx)
         .fetch_all(&mut *conn)
         .await?;
 
-        for patient in all_patients {
+        for patient in candidates {
             // Skip if already added as high confidence duplicate
             if duplicates.iter().any(|d| d.patient_id == patient.id) {
                 continue;
@@ -440,6 +641,65 @@ impl PatientService {
         Ok(duplicates)
     }
 
+    /// Compute the blind indexes of a batch of patients saved before they
+    /// existed, returning how many were indexed
+    ///
+    /// Anonymized and merged patients are read-only and have nothing left
+    /// to look up, so they are skipped.
+    pub async fn backfill_blind_indexes(&self) -> Result<usize> {
+        let mut tx = self.pool.begin().await?;
+        apply_rls_context(&mut tx, SYSTEM_USER_ID, SYSTEM_ROLE).await?;
+        include_trashed_records(&mut tx).await?;
+
+        let patients = sqlx::query_as::<_, Patient>(
+            r#"
+            SELECT * FROM patients
+            WHERE (last_name_bidx IS NULL OR search_trigram_bidx IS NULL)
+              AND anonymized_at IS NULL AND merged_at IS NULL
+            ORDER BY created_at
+            LIMIT $1
+            "#,
+        )
+        .bind(BACKFILL_BATCH_SIZE)
+        .fetch_all(&mut *tx)
+        .await
+        .context("Failed to fetch patients without blind indexes")?;
+
+        let mut indexed = 0;
+        for patient in &patients {
+            let blind_indexes = match PatientBlindIndexes::of_patient(&self.encryption_key, patient) {
+                Ok(blind_indexes) => blind_indexes,
+                Err(e) => {
+                    warn!("Patient {} cannot be indexed: {}", patient.id, e);
+                    continue;
+                }
+            };
+
+            sqlx::query(
+                r#"
+                UPDATE patients
+                SET fiscal_code_bidx = $2, last_name_bidx = $3, last_name_prefix_bidx = $4,
+                    phone_bidx = $5, email_bidx = $6, search_trigram_bidx = $7
+                WHERE id = $1
+                "#,
+            )
+            .bind(patient.id)
+            .bind(blind_indexes.fiscal_code)
+            .bind(blind_indexes.last_name)
+            .bind(blind_indexes.last_name_prefixes)
+            .bind(blind_indexes.phones)
+            .bind(blind_indexes.email)
+            .bind(blind_indexes.trigrams)
+            .execute(&mut *tx)
+            .await
+            .context("Failed to store patient blind indexes")?;
+            indexed += 1;
+        }
+
+        tx.commit().await?;
+        Ok(indexed)
+    }
+
     /// Get patient statistics
     pub async fn get_statistics<'c>(
         &self,
@@ -0,0 +1,595 @@
+/*!
+ * Prescription Refill Service
+ *
+ * Refill tracking for active prescriptions:
+ * - `estimate_days_supply` works out how long the prescribed quantity lasts
+ *   from the (free text) frequency; the prescription service stores it on
+ *   every create/update and the expected refill date follows from it
+ * - `record_refill` uses up one refill, which moves the expected refill date
+ *   forward
+ * - `list_overdue` lists active prescriptions whose supply has run out
+ * - `send_reminders` emails those patients a REFILL_REMINDER through the
+ *   notification queue, at most once per reminder interval
+ *
+ * Frequencies that cannot be read (or "as needed") leave `days_supply` NULL,
+ * and such prescriptions are never overdue.
+ */
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::{
+    db::rls::apply_rls_context,
+    models::{
+        OverdueRefill, OverdueRefillsQuery, RefillReminderSkipReason, RefillRemindersResponse,
+        SendRefillRemindersRequest, SkippedRefillReminder,
+    },
+    services::NotificationService,
+    utils::{encryption::EncryptionKey, AppError, Result},
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use regex::Regex;
+use sqlx::{PgPool, Postgres, Transaction};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Maximum prescriptions in the overdue list
+const OVERDUE_LIMIT: i64 = 500;
+
+/// Active prescriptions whose days_supply is filled in per call
+const BACKFILL_BATCH: i64 = 500;
+
+static EVERY_N_HOURS: OnceLock<Regex> = OnceLock::new();
+static TIMES_PER_PERIOD: OnceLock<Regex> = OnceLock::new();
+static UNITS_PER_DOSE: OnceLock<Regex> = OnceLock::new();
+
+/// "ogni 8 ore", "every 12 hours", "q6h"
+fn every_n_hours_regex() -> &'static Regex {
+    EVERY_N_HOURS.get_or_init(|| {
+        Regex::new(r"(?:ogni|every)\s*(\d+)\s*(?:ore|hours?|h)\b|\bq\s*(\d+)\s*h\b").unwrap()
+    })
+}
+
+/// "2 volte al giorno", "3 times a day", "1 volta a settimana", "2x/die"
+fn times_per_period_regex() -> &'static Regex {
+    TIMES_PER_PERIOD.get_or_init(|| {
+        Regex::new(r"(\d+)\s*(?:volt[ae]|times?|x)\b[^\d]*?(giorn|die|day|daily|settiman|week)")
+            .unwrap()
+    })
+}
+
+/// "2 compresse", "1 cpr", "2 puffs" (units taken per dose)
+fn units_per_dose_regex() -> &'static Regex {
+    UNITS_PER_DOSE.get_or_init(|| {
+        Regex::new(r"(\d+(?:[.,]\d+)?)\s*(?:compress|cpr|capsul|cps|tablet|tabs?\b|pills?\b|bustin|sachet|puff|spruzz|gocc|drops?\b|suppost|cerott|patch|fial|ampoul)").unwrap()
+    })
+}
+
+/// Doses per day read from a frequency, or None for "as needed" or text
+/// that cannot be read
+fn doses_per_day(frequency: &str) -> Option<f64> {
+    const AS_NEEDED: [&str; 5] = [
+        "prn",
+        "al bisogno",
+        "as needed",
+        "if needed",
+        "se necessario",
+    ];
+    if AS_NEEDED.iter().any(|m| frequency.contains(m)) {
+        return None;
+    }
+
+    if let Some(caps) = every_n_hours_regex().captures(frequency) {
+        let hours: f64 = caps
+            .get(1)
+            .or_else(|| caps.get(2))
+            .and_then(|m| m.as_str().parse().ok())?;
+        return (hours > 0.0).then_some(24.0 / hours);
+    }
+
+    if let Some(caps) = times_per_period_regex().captures(frequency) {
+        let times: f64 = caps[1].parse().ok()?;
+        let weekly = matches!(&caps[2], "settiman" | "week");
+        return (times > 0.0).then_some(if weekly { times / 7.0 } else { times });
+    }
+
+    let has_word = |words: &[&str]| {
+        frequency
+            .split(|c: char| !c.is_alphanumeric())
+            .any(|token| words.contains(&token))
+    };
+    let has_phrase = |phrases: &[&str]| phrases.iter().any(|p| frequency.contains(p));
+
+    let times = if has_word(&["qid"]) || has_phrase(&["quattro volte", "four times"]) {
+        Some(4.0)
+    } else if has_word(&["tid"]) || has_phrase(&["tre volte", "three times"]) {
+        Some(3.0)
+    } else if has_word(&["bid"]) || has_phrase(&["due volte", "twice"]) {
+        Some(2.0)
+    } else if has_word(&["qd", "od"]) || has_phrase(&["una volta", "once"]) {
+        Some(1.0)
+    } else {
+        None
+    };
+    if has_phrase(&["settiman", "week"]) {
+        return Some(times.unwrap_or(1.0) / 7.0);
+    }
+    if times.is_some() {
+        return times;
+    }
+
+    // "mattino e sera", "morning and evening", "a colazione, pranzo e cena"
+    const TIMES_OF_DAY: [&[&str]; 4] = [
+        &["mattin", "colazione", "morning", "breakfast"],
+        &["pranzo", "mezzogiorno", "noon", "lunch"],
+        &["sera", "cena", "evening", "dinner"],
+        &["notte", "coricarsi", "bedtime", "night"],
+    ];
+    let times_of_day = TIMES_OF_DAY
+        .iter()
+        .filter(|words| has_phrase(words))
+        .count();
+    if times_of_day > 0 {
+        return Some(times_of_day as f64);
+    }
+
+    if has_word(&["die", "daily"]) || has_phrase(&["al giorno", "a day", "per day", "giornalier"]) {
+        return Some(1.0);
+    }
+
+    None
+}
+
+/// Estimate how many days the prescribed quantity lasts
+///
+/// `quantity` is in the units the frequency doses ("2 compresse 2 volte al
+/// giorno" uses 4 a day). Returns None without a quantity, for "as needed"
+/// prescriptions and for frequencies that cannot be read.
+pub fn estimate_days_supply(frequency: &str, quantity: Option<i32>) -> Option<i32> {
+    let quantity = quantity.filter(|q| *q > 0)?;
+    let frequency = frequency.to_lowercase();
+
+    let doses = doses_per_day(&frequency)?;
+    let units_per_dose = units_per_dose_regex()
+        .captures(&frequency)
+        .and_then(|caps| caps[1].replace(',', ".").parse::<f64>().ok())
+        .filter(|units| *units > 0.0)
+        .unwrap_or(1.0);
+
+    // The epsilon keeps 4 weekly doses at 28 days despite float rounding
+    let days = (quantity as f64 / (doses * units_per_dose) + 1e-9).floor();
+    Some(days.clamp(1.0, i32::MAX as f64) as i32)
+}
+
+/// Store the estimated days supply of a prescription
+///
+/// Runs in the caller's transaction; clears it when the frequency cannot be
+/// read.
+pub async fn store_days_supply(
+    tx: &mut Transaction<'_, Postgres>,
+    prescription_id: Uuid,
+    frequency: &str,
+    quantity: Option<i32>,
+) -> Result<Option<i32>> {
+    let days_supply = estimate_days_supply(frequency, quantity);
+
+    sqlx::query("UPDATE prescriptions SET days_supply = $2 WHERE id = $1")
+        .bind(prescription_id)
+        .bind(days_supply)
+        .execute(&mut **tx)
+        .await?;
+
+    Ok(days_supply)
+}
+
+/// Overdue prescription row (patient data still encrypted)
+#[derive(Debug, sqlx::FromRow)]
+struct OverdueRefillRow {
+    prescription_id: Uuid,
+    patient_id: Uuid,
+    provider_id: Uuid,
+    medication_name: String,
+    dosage: String,
+    frequency: String,
+    days_supply: i32,
+    expected_refill_date: NaiveDate,
+    last_refill_date: Option<NaiveDate>,
+    refills_remaining: Option<i32>,
+    last_refill_reminder_at: Option<DateTime<Utc>>,
+    patient_first_name: String,
+    patient_last_name: String,
+    patient_email: Option<String>,
+    email_address_override: Option<String>,
+    email_enabled: Option<bool>,
+    provider_first_name: String,
+    provider_last_name: String,
+}
+
+/// Prescription refill service
+pub struct PrescriptionRefillService {
+    pool: PgPool,
+    encryption_key: EncryptionKey,
+}
+
+impl PrescriptionRefillService {
+    /// Create a new prescription refill service
+    pub fn new(pool: PgPool, encryption_key: EncryptionKey) -> Self {
+        Self {
+            pool,
+            encryption_key,
+        }
+    }
+
+    /// Use up one refill of an active prescription
+    ///
+    /// The refill tracking trigger sets the last refill date (and completes
+    /// the prescription on its last refill). Fails with NotFound for an
+    /// unknown prescription and with Conflict if it is not active or has no
+    /// refills left.
+    pub async fn record_refill(
+        &self,
+        prescription_id: Uuid,
+        user_id: Uuid,
+        role: &str,
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        apply_rls_context(&mut tx, user_id, role)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        let current: Option<(String, Option<i32>)> = sqlx::query_as(
+            "SELECT status, refills_remaining FROM prescriptions WHERE id = $1 FOR UPDATE",
+        )
+        .bind(prescription_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let (status, refills_remaining) =
+            current.ok_or_else(|| AppError::NotFound("Prescription not found".to_string()))?;
+
+        if status != "ACTIVE" {
+            return Err(AppError::Conflict(format!(
+                "Cannot refill a {} prescription",
+                status.to_lowercase()
+            )));
+        }
+        if refills_remaining.unwrap_or(0) <= 0 {
+            return Err(AppError::Conflict(
+                "No refills left; a new prescription is needed".to_string(),
+            ));
+        }
+
+        sqlx::query(
+            r#"
+            UPDATE prescriptions
+            SET refills_remaining = refills_remaining - 1,
+                last_refill_reminder_at = NULL,
+                updated_at = NOW(),
+                updated_by = $2
+            WHERE id = $1
+            "#,
+        )
+        .bind(prescription_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        info!("Recorded refill of prescription {}", prescription_id);
+        Ok(())
+    }
+
+    /// Active prescriptions whose supply ran out more than `grace_days` ago,
+    /// most overdue first
+    pub async fn list_overdue(
+        &self,
+        query: &OverdueRefillsQuery,
+        user_id: Uuid,
+        role: &str,
+    ) -> Result<Vec<OverdueRefill>> {
+        let rows = self
+            .fetch_overdue(query.grace_days, query.patient_id, &[], user_id, role)
+            .await?;
+
+        let today = Utc::now().date_naive();
+        rows.iter()
+            .map(|row| {
+                Ok(OverdueRefill {
+                    prescription_id: row.prescription_id,
+                    patient_id: row.patient_id,
+                    patient_name: format!(
+                        "{} {}",
+                        self.decrypt(&row.patient_first_name)?,
+                        self.decrypt(&row.patient_last_name)?
+                    ),
+                    provider_id: row.provider_id,
+                    medication_name: self.decrypt(&row.medication_name)?,
+                    dosage: self.decrypt(&row.dosage)?,
+                    frequency: self.decrypt(&row.frequency)?,
+                    days_supply: row.days_supply,
+                    expected_refill_date: row.expected_refill_date,
+                    days_overdue: (today - row.expected_refill_date).num_days(),
+                    last_refill_date: row.last_refill_date,
+                    refills_remaining: row.refills_remaining,
+                    needs_new_prescription: row.refills_remaining.unwrap_or(0) <= 0,
+                    last_refill_reminder_at: row.last_refill_reminder_at,
+                })
+            })
+            .collect()
+    }
+
+    /// Queue a REFILL_REMINDER email for overdue prescriptions
+    ///
+    /// Patients who opted out of email, have no address, or were reminded
+    /// about the same prescription within the interval are skipped.
+    pub async fn send_reminders(
+        &self,
+        notification_service: &NotificationService,
+        request: &SendRefillRemindersRequest,
+        user_id: Uuid,
+        role: &str,
+    ) -> Result<RefillRemindersResponse> {
+        let rows = self
+            .fetch_overdue(
+                request.grace_days,
+                None,
+                &request.prescription_ids,
+                user_id,
+                role,
+            )
+            .await?;
+
+        let reminded_after = Utc::now() - chrono::Duration::days(request.interval_days() as i64);
+        let mut response = RefillRemindersResponse::default();
+
+        for row in rows {
+            let skip_reason = if row
+                .last_refill_reminder_at
+                .is_some_and(|at| at > reminded_after)
+            {
+                Some(RefillReminderSkipReason::RecentlyReminded)
+            } else if row.email_enabled == Some(false) {
+                Some(RefillReminderSkipReason::EmailDisabled)
+            } else if row
+                .email_address_override
+                .as_ref()
+                .or(row.patient_email.as_ref())
+                .is_none()
+            {
+                Some(RefillReminderSkipReason::NoEmail)
+            } else {
+                None
+            };
+            if let Some(reason) = skip_reason {
+                response.skipped.push(SkippedRefillReminder {
+                    prescription_id: row.prescription_id,
+                    reason,
+                });
+                continue;
+            }
+
+            let encrypted_email = row
+                .email_address_override
+                .or(row.patient_email)
+                .unwrap_or_default();
+            let patient_name = format!(
+                "{} {}",
+                self.decrypt(&row.patient_first_name)?,
+                self.decrypt(&row.patient_last_name)?
+            );
+            let doctor_name = format!("Dr. {} {}", row.provider_first_name, row.provider_last_name);
+
+            notification_service
+                .queue_refill_reminder(
+                    row.patient_id,
+                    row.prescription_id,
+                    &self.decrypt(&encrypted_email)?,
+                    &patient_name,
+                    &self.decrypt(&row.medication_name)?,
+                    row.expected_refill_date,
+                    &doctor_name,
+                    user_id,
+                )
+                .await
+                .map_err(|e| AppError::Internal(e.to_string()))?;
+
+            response.queued.push(row.prescription_id);
+        }
+
+        if !response.queued.is_empty() {
+            let mut tx = self.pool.begin().await?;
+            apply_rls_context(&mut tx, user_id, role)
+                .await
+                .map_err(|e| AppError::Internal(e.to_string()))?;
+            sqlx::query(
+                "UPDATE prescriptions SET last_refill_reminder_at = NOW() WHERE id = ANY($1)",
+            )
+            .bind(&response.queued)
+            .execute(&mut *tx)
+            .await?;
+            tx.commit().await?;
+        }
+
+        info!(
+            "Queued {} refill reminders ({} skipped)",
+            response.queued.len(),
+            response.skipped.len()
+        );
+        Ok(response)
+    }
+
+    /// Overdue prescriptions, optionally narrowed to a patient or a list of
+    /// prescriptions
+    ///
+    /// Active prescriptions saved before refill tracking have no days
+    /// supply yet; it is estimated first so they are not missed.
+    async fn fetch_overdue(
+        &self,
+        grace_days: i32,
+        patient_id: Option<Uuid>,
+        prescription_ids: &[Uuid],
+        user_id: Uuid,
+        role: &str,
+    ) -> Result<Vec<OverdueRefillRow>> {
+        let mut tx = self.pool.begin().await?;
+        apply_rls_context(&mut tx, user_id, role)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        self.backfill_days_supply(&mut tx).await?;
+
+        let rows = sqlx::query_as::<_, OverdueRefillRow>(
+            r#"
+            SELECT
+                rx.id AS prescription_id,
+                rx.patient_id,
+                rx.provider_id,
+                rx.medication_name,
+                rx.dosage,
+                rx.frequency,
+                rx.days_supply,
+                rx.expected_refill_date,
+                rx.last_refill_date,
+                rx.refills_remaining,
+                rx.last_refill_reminder_at,
+                p.first_name AS patient_first_name,
+                p.last_name AS patient_last_name,
+                p.email AS patient_email,
+                pnp.email_address_override,
+                pnp.email_enabled,
+                u.first_name AS provider_first_name,
+                u.last_name AS provider_last_name
+            FROM prescriptions rx
+            INNER JOIN patients p ON p.id = rx.patient_id
+            INNER JOIN users u ON u.id = rx.provider_id
+            LEFT JOIN patient_notification_preferences pnp ON pnp.patient_id = rx.patient_id
+            WHERE rx.status = 'ACTIVE'
+              AND rx.expected_refill_date < CURRENT_DATE - $1::INT
+              AND p.anonymized_at IS NULL
+              AND ($2::UUID IS NULL OR rx.patient_id = $2)
+              AND (cardinality($3::UUID[]) = 0 OR rx.id = ANY($3))
+            ORDER BY rx.expected_refill_date, rx.created_at
+            LIMIT $4
+            "#,
+        )
+        .bind(grace_days)
+        .bind(patient_id)
+        .bind(prescription_ids)
+        .bind(OVERDUE_LIMIT)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(rows)
+    }
+
+    /// Estimate the days supply of active prescriptions that have none yet
+    async fn backfill_days_supply(&self, tx: &mut Transaction<'_, Postgres>) -> Result<()> {
+        let pending: Vec<(Uuid, String, i32)> = sqlx::query_as(
+            r#"
+            SELECT id, frequency, quantity
+            FROM prescriptions
+            WHERE status = 'ACTIVE'
+              AND days_supply IS NULL
+              AND quantity > 0
+            LIMIT $1
+            "#,
+        )
+        .bind(BACKFILL_BATCH)
+        .fetch_all(&mut **tx)
+        .await?;
+
+        let mut estimated: HashMap<Uuid, i32> = HashMap::new();
+        for (id, frequency, quantity) in pending {
+            match self.decrypt(&frequency) {
+                Ok(frequency) => {
+                    if let Some(days) = estimate_days_supply(&frequency, Some(quantity)) {
+                        estimated.insert(id, days);
+                    }
+                }
+                Err(e) => warn!("Skipping days supply of prescription {}: {}", id, e),
+            }
+        }
+        if estimated.is_empty() {
+            return Ok(());
+        }
+
+        let (ids, days): (Vec<Uuid>, Vec<i32>) = estimated.into_iter().unzip();
+        sqlx::query(
+            r#"
+            UPDATE prescriptions rx
+            SET days_supply = v.days_supply
+            FROM UNNEST($1::UUID[], $2::INT[]) AS v (id, days_supply)
+            WHERE rx.id = v.id
+            "#,
+        )
+        .bind(&ids)
+        .bind(&days)
+        .execute(&mut **tx)
+        .await?;
+
+        info!("Estimated days supply of {} prescriptions", ids.len());
+        Ok(())
+    }
+
+    fn decrypt(&self, value: &str) -> Result<String> {
+        self.encryption_key
+            .decrypt(value)
+            .map_err(|e| AppError::Internal(format!("Failed to decrypt prescription data: {}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_days_supply_italian() {
+        assert_eq!(
+            estimate_days_supply("1 compressa al giorno", Some(30)),
+            Some(30)
+        );
+        assert_eq!(
+            estimate_days_supply("2 volte al giorno", Some(60)),
+            Some(30)
+        );
+        assert_eq!(
+            estimate_days_supply("1 cpr mattino e sera", Some(28)),
+            Some(14)
+        );
+        assert_eq!(
+            estimate_days_supply("2 compresse 3 volte al giorno", Some(60)),
+            Some(10)
+        );
+        assert_eq!(estimate_days_supply("ogni 8 ore", Some(21)), Some(7));
+        assert_eq!(
+            estimate_days_supply("1 volta a settimana", Some(4)),
+            Some(28)
+        );
+    }
+
+    #[test]
+    fn test_estimate_days_supply_english() {
+        assert_eq!(estimate_days_supply("Once daily", Some(30)), Some(30));
+        assert_eq!(estimate_days_supply("BID", Some(60)), Some(30));
+        assert_eq!(estimate_days_supply("TID with meals", Some(90)), Some(30));
+        assert_eq!(estimate_days_supply("q6h", Some(20)), Some(5));
+        assert_eq!(
+            estimate_days_supply("2 puffs twice a day", Some(200)),
+            Some(50)
+        );
+        assert_eq!(estimate_days_supply("At bedtime", Some(10)), Some(10));
+        assert_eq!(estimate_days_supply("twice a week", Some(8)), Some(28));
+    }
+
+    #[test]
+    fn test_estimate_days_supply_unknown() {
+        assert_eq!(estimate_days_supply("al bisogno", Some(20)), None);
+        assert_eq!(estimate_days_supply("PRN for pain", Some(20)), None);
+        assert_eq!(estimate_days_supply("come indicato", Some(20)), None);
+        assert_eq!(estimate_days_supply("BID", None), None);
+        assert_eq!(estimate_days_supply("BID", Some(0)), None);
+        // Never less than a day
+        assert_eq!(estimate_days_supply("QID", Some(2)), Some(1));
+    }
+}
@@ -3,32 +3,51 @@
  *
  * Handles business logic for prescription management including:
  * - CRUD operations for prescriptions
- * - Medication search
- * - Refill tracking
+ * - Medication search (ranked per prescriber, see medication_favorite_service)
+ * - Refill tracking (days supply re-estimated whenever frequency or
+ *   quantity change, see prescription_refill_service)
  * - Drug interaction checking (placeholder)
+ * - Drug-allergy checking against the patient's active allergies, with an
+ *   audited override reason required to prescribe anyway
  * - Encryption/decryption of medication data
  * - Audit logging for all operations
  */
 
+use crate::db::rls::{apply_rls_context, target_trash_record};
 use crate::{
     models::{
-        AuditAction, CreatePrescriptionRequest, DrugInteractionWarning, MedicationForm,
-        Prescription, PrescriptionResponse, PrescriptionStatus,
+        AuditAction, CreatePrescriptionRequest, DrugAllergyAlert, DrugInteractionWarning,
+        MedicationDefaults, MedicationForm, Prescription, PrescriptionResponse, PrescriptionStatus,
         UpdatePrescriptionRequest,
     },
-    utils::encryption::EncryptionKey,
+    services::{
+        drug_allergy_check::{check_drug_allergies, PrescribedDrug},
+        medication_favorite_service::record_medication_usage,
+        prescription_refill_service::store_days_supply,
+        PatientAllergyService,
+    },
+    utils::{encryption::EncryptionKey, AppError},
 };
 use anyhow::{Context, Result};
-use sqlx::PgPool;
+use sqlx::{PgPool, Postgres, Transaction};
 use uuid::Uuid;
 
 /// Medication search result
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
 pub struct MedicationSearchResult {
     pub name: String,
     pub generic_name: Option<String>,
     pub form: Option<MedicationForm>,
     pub common_dosages: Vec<String>,
+    /// One of the prescriber's favorites (listed first)
+    #[serde(default)]
+    pub is_favorite: bool,
+    /// Times the prescriber has prescribed it
+    #[serde(default)]
+    pub usage_count: i32,
+    /// Default dosing of the favorite
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub defaults: Option<MedicationDefaults>,
 }
 
 /// Prescription Service
@@ -46,28 +65,6 @@ impl PrescriptionService {
         }
     }
 
-    /// Set RLS context variables for database connection
-    async fn set_rls_context(
-        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
-        user_id: Uuid,
-        role: &str,
-    ) -> Result<()> {
-        // Use set_config() for parameterized queries (security best practice)
-        sqlx::query("SELECT set_config('app.current_user_id', $1, true)")
-            .bind(user_id.to_string())
-            .execute(&mut **tx)
-            .await
-            .context("Failed to set RLS user context")?;
-
-        sqlx::query("SELECT set_config('app.current_user_role', $1, true)")
-            .bind(role)
-            .execute(&mut **tx)
-            .await
-            .context("Failed to set RLS role context")?;
-
-        Ok(())
-    }
-
     /// Create a new prescription
     pub async fn create_prescription(
         &self,
@@ -79,7 +76,7 @@ impl PrescriptionService {
         let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
 
         // Set RLS context variables
-        Self::set_rls_context(&mut tx, created_by, role).await?;
+        apply_rls_context(&mut tx, created_by, role).await?;
 
         // Use visit_id directly (it's already Option<Uuid>)
         let visit_id = data.visit_id;
@@ -110,6 +107,30 @@ impl PrescriptionService {
             }
         }
 
+        // A drug matching an active allergy needs an explicit override reason
+        let allergy_alerts = self
+            .find_allergy_alerts(
+                &mut tx,
+                patient_id,
+                &data.medication_name,
+                data.generic_name.as_deref(),
+            )
+            .await?;
+        let allergy_override_reason = data
+            .allergy_override_reason
+            .as_deref()
+            .map(str::trim)
+            .filter(|r| !r.is_empty());
+        if !allergy_alerts.is_empty() && allergy_override_reason.is_none() {
+            let substances: Vec<&str> = allergy_alerts.iter().map(|a| a.substance.as_str()).collect();
+            return Err(AppError::Conflict(format!(
+                "{} conflicts with the patient's allergies ({}); an allergy_override_reason is required to prescribe it",
+                data.medication_name,
+                substances.join(", ")
+            ))
+            .into());
+        }
+
         // Encrypt medication fields
         let encrypted_medication_name = self.encryption_key.encrypt(&data.medication_name)?;
         let encrypted_generic_name = data
@@ -236,6 +257,10 @@ impl PrescriptionService {
         .await
         .context("Failed to create prescription")?;
 
+        store_days_supply(&mut tx, prescription.id, &data.frequency, data.quantity).await?;
+        record_medication_usage(&mut tx, &self.encryption_key, created_by, &data.medication_name)
+            .await?;
+
         // Use frontend-provided interaction warnings if available (user already confirmed these)
         // Otherwise, do server-side check as fallback
         let interactions = if let Some(ref warnings) = data.interaction_warnings {
@@ -266,6 +291,18 @@ impl PrescriptionService {
             .context("Failed to update interaction warnings")?;
         }
 
+        if let Some(reason) = allergy_override_reason {
+            self.record_allergy_overrides(
+                &mut tx,
+                prescription.id,
+                patient_id,
+                &allergy_alerts,
+                reason,
+                created_by,
+            )
+            .await?;
+        }
+
         // Commit transaction before audit logging
         tx.commit().await.context("Failed to commit transaction")?;
 
@@ -280,7 +317,30 @@ impl PrescriptionService {
         .await?;
 
         // Convert to response
-        self.prescription_to_response(prescription).await
+        let mut response = self.prescription_to_response(prescription).await?;
+        response.allergy_alerts = allergy_alerts;
+        Ok(response)
+    }
+
+    /// Check a drug against the patient's active allergies without prescribing it
+    pub async fn check_allergies(
+        &self,
+        patient_id: Uuid,
+        medication_name: &str,
+        generic_name: Option<&str>,
+        user_id: Uuid,
+        role: &str,
+    ) -> Result<Vec<DrugAllergyAlert>> {
+        let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
+        apply_rls_context(&mut tx, user_id, role).await?;
+
+        let alerts = self
+            .find_allergy_alerts(&mut tx, patient_id, medication_name, generic_name)
+            .await?;
+
+        tx.commit().await.context("Failed to commit transaction")?;
+
+        Ok(alerts)
     }
 
     /// Get prescription by ID
@@ -292,7 +352,7 @@ impl PrescriptionService {
     ) -> Result<Option<PrescriptionResponse>> {
         // Start transaction for RLS context
         let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-        Self::set_rls_context(&mut tx, user_id, role).await?;
+        apply_rls_context(&mut tx, user_id, role).await?;
 
         let prescription = sqlx::query_as!(
             Prescription,
@@ -373,7 +433,7 @@ impl PrescriptionService {
     ) -> Result<(Vec<PrescriptionResponse>, i64)> {
         // Start transaction for RLS context
         let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-        Self::set_rls_context(&mut tx, user_id, role).await?;
+        apply_rls_context(&mut tx, user_id, role).await?;
 
         // Build dynamic WHERE clause
         let mut conditions: Vec<String> = Vec::new();
@@ -481,7 +541,7 @@ impl PrescriptionService {
     ) -> Result<Vec<PrescriptionResponse>> {
         // Start transaction for RLS context
         let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-        Self::set_rls_context(&mut tx, user_id, role).await?;
+        apply_rls_context(&mut tx, user_id, role).await?;
 
         let prescriptions = if active_only {
             sqlx::query_as!(
@@ -554,7 +614,7 @@ impl PrescriptionService {
     ) -> Result<Vec<PrescriptionResponse>> {
         // Start transaction for RLS context
         let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-        Self::set_rls_context(&mut tx, user_id, role).await?;
+        apply_rls_context(&mut tx, user_id, role).await?;
 
         let prescriptions = sqlx::query_as!(
             Prescription,
@@ -601,7 +661,7 @@ impl PrescriptionService {
     ) -> Result<PrescriptionResponse> {
         // Start transaction for RLS context
         let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-        Self::set_rls_context(&mut tx, updated_by, role).await?;
+        apply_rls_context(&mut tx, updated_by, role).await?;
 
         // Get existing prescription
         let existing = sqlx::query!(
@@ -696,6 +756,15 @@ impl PrescriptionService {
         .await
         .context("Failed to update prescription")?;
 
+        // How long the supply lasts depends on both
+        if data.frequency.is_some() || data.quantity.is_some() {
+            let frequency = match data.frequency {
+                Some(ref frequency) => frequency.clone(),
+                None => self.encryption_key.decrypt(&prescription.frequency)?,
+            };
+            store_days_supply(&mut tx, prescription.id, &frequency, prescription.quantity).await?;
+        }
+
         tx.commit().await.context("Failed to commit transaction")?;
 
         // Log audit entry
@@ -722,7 +791,7 @@ impl PrescriptionService {
     ) -> Result<PrescriptionResponse> {
         // Start transaction for RLS context
         let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-        Self::set_rls_context(&mut tx, discontinued_by, role).await?;
+        apply_rls_context(&mut tx, discontinued_by, role).await?;
 
         // Get existing prescription
         let existing = sqlx::query_as!(
@@ -822,7 +891,7 @@ impl PrescriptionService {
         role: &str,
     ) -> Result<PrescriptionResponse> {
         let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-        Self::set_rls_context(&mut tx, cancelled_by, role).await?;
+        apply_rls_context(&mut tx, cancelled_by, role).await?;
 
         // Get existing prescription
         let existing = sqlx::query_as!(
@@ -921,7 +990,7 @@ impl PrescriptionService {
         role: &str,
     ) -> Result<PrescriptionResponse> {
         let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-        Self::set_rls_context(&mut tx, updated_by, role).await?;
+        apply_rls_context(&mut tx, updated_by, role).await?;
 
         let existing = sqlx::query_as!(
             Prescription,
@@ -1012,7 +1081,7 @@ impl PrescriptionService {
         role: &str,
     ) -> Result<PrescriptionResponse> {
         let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-        Self::set_rls_context(&mut tx, updated_by, role).await?;
+        apply_rls_context(&mut tx, updated_by, role).await?;
 
         let existing = sqlx::query_as!(
             Prescription,
@@ -1100,7 +1169,7 @@ impl PrescriptionService {
         role: &str,
     ) -> Result<PrescriptionResponse> {
         let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-        Self::set_rls_context(&mut tx, updated_by, role).await?;
+        apply_rls_context(&mut tx, updated_by, role).await?;
 
         let existing = sqlx::query_as!(
             Prescription,
@@ -1178,6 +1247,9 @@ impl PrescriptionService {
     }
 
     /// Delete prescription
+    ///
+    /// The prescription goes to the recycle bin, where it can be restored
+    /// until the RECYCLE_BIN retention rule purges it.
     pub async fn delete_prescription(
         &self,
         id: Uuid,
@@ -1186,7 +1258,7 @@ impl PrescriptionService {
     ) -> Result<()> {
         // Start transaction for RLS context
         let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-        Self::set_rls_context(&mut tx, deleted_by, role).await?;
+        apply_rls_context(&mut tx, deleted_by, role).await?;
 
         // Get patient_id for audit logging
         let prescription = sqlx::query!(
@@ -1198,11 +1270,20 @@ impl PrescriptionService {
         .context("Failed to check prescription existence")?
         .ok_or_else(|| anyhow::anyhow!("Prescription not found"))?;
 
-        // Delete prescription
-        let result = sqlx::query!("DELETE FROM prescriptions WHERE id = $1", id)
-            .execute(&mut *tx)
-            .await
-            .context("Failed to delete prescription")?;
+        // Move the prescription to the recycle bin
+        target_trash_record(&mut tx, id).await?;
+        let result = sqlx::query(
+            r#"
+            UPDATE prescriptions
+            SET deleted_at = NOW(), deleted_by = $2
+            WHERE id = $1 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(id)
+        .bind(deleted_by)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to delete prescription")?;
 
         // Verify the prescription was actually deleted
         if result.rows_affected() == 0 {
@@ -1296,6 +1377,7 @@ impl PrescriptionService {
                     generic_name: row.generic_name,
                     form,
                     common_dosages,
+                    ..Default::default()
                 }
             })
             .collect();
@@ -1320,7 +1402,7 @@ impl PrescriptionService {
 
         // Start transaction and set RLS context
         let mut tx = self.pool.begin().await?;
-        Self::set_rls_context(&mut tx, created_by, role).await?;
+        apply_rls_context(&mut tx, created_by, role).await?;
 
         // Generate a unique ID and pseudo-AIC code for custom medications
         // The unique_aic_code constraint has NULLS NOT DISTINCT, so we need a unique value
@@ -1376,6 +1458,89 @@ impl PrescriptionService {
         Ok(vec![])
     }
 
+    /// Alerts for the patient's active allergies matching a drug
+    ///
+    /// The ATC code is looked up in the medications table so brand names
+    /// are matched by class too.
+    async fn find_allergy_alerts(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        patient_id: Uuid,
+        medication_name: &str,
+        generic_name: Option<&str>,
+    ) -> Result<Vec<DrugAllergyAlert>> {
+        let atc_code: Option<String> = sqlx::query_scalar(
+            r#"
+            SELECT atc_code
+            FROM medications
+            WHERE is_active = true
+              AND atc_code IS NOT NULL
+              AND (LOWER(name) = LOWER($1) OR LOWER(generic_name) = LOWER($2))
+            ORDER BY (LOWER(name) = LOWER($1)) DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(medication_name)
+        .bind(generic_name.unwrap_or(medication_name))
+        .fetch_optional(&mut **tx)
+        .await
+        .context("Failed to look up medication ATC code")?;
+
+        let allergies =
+            PatientAllergyService::active_allergy_records(&mut **tx, &self.encryption_key, patient_id)
+                .await
+                .context("Failed to load patient allergies")?;
+
+        Ok(check_drug_allergies(
+            PrescribedDrug {
+                name: medication_name,
+                generic_name,
+                atc_code: atc_code.as_deref(),
+            },
+            &allergies,
+        ))
+    }
+
+    /// Record the allergy alerts a prescriber overrode, with the reason
+    async fn record_allergy_overrides(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        prescription_id: Uuid,
+        patient_id: Uuid,
+        alerts: &[DrugAllergyAlert],
+        reason: &str,
+        overridden_by: Uuid,
+    ) -> Result<()> {
+        let encrypted_reason = self.encryption_key.encrypt(reason)?;
+
+        for alert in alerts {
+            let encrypted_alert = self
+                .encryption_key
+                .encrypt(&serde_json::to_string(alert)?)?;
+
+            sqlx::query(
+                r#"
+                INSERT INTO prescription_allergy_overrides (
+                    prescription_id, patient_id, allergy_id, match_type, alert, reason, overridden_by
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+                "#,
+            )
+            .bind(prescription_id)
+            .bind(patient_id)
+            .bind(alert.allergy_id)
+            .bind(alert.match_type.as_str())
+            .bind(&encrypted_alert)
+            .bind(&encrypted_reason)
+            .bind(overridden_by)
+            .execute(&mut **tx)
+            .await
+            .context("Failed to record allergy override")?;
+        }
+
+        Ok(())
+    }
+
     /// Convert prescription model to response (decrypting fields)
     async fn prescription_to_response(&self, prescription: Prescription) -> Result<PrescriptionResponse> {
         // Decrypt medication fields
@@ -1444,6 +1609,7 @@ impl PrescriptionService {
             last_refill_date: prescription.last_refill_date,
             has_interactions: prescription.has_interactions,
             interaction_warnings,
+            allergy_alerts: Vec::new(),
             e_prescription_id: prescription.e_prescription_id,
             e_prescription_sent_at: prescription.e_prescription_sent_at,
             e_prescription_status: prescription.e_prescription_status,
@@ -1510,6 +1676,7 @@ mod tests {
             generic_name: Some("Ibuprofen".to_string()),
             form: Some(MedicationForm::Tablet),
             common_dosages: vec!["200mg".to_string(), "400mg".to_string(), "600mg".to_string()],
+            ..Default::default()
         };
 
         assert_eq!(result.name, "Ibuprofen");
@@ -1525,6 +1692,7 @@ mod tests {
             generic_name: Some("Amoxicillin Trihydrate".to_string()),
             form: Some(MedicationForm::Capsule),
             common_dosages: vec!["250mg".to_string(), "500mg".to_string()],
+            ..Default::default()
         };
 
         let json = serde_json::to_string(&result).expect("Should serialize");
@@ -1545,6 +1713,7 @@ mod tests {
             generic_name: None,
             form: None,
             common_dosages: vec![],
+            ..Default::default()
         };
 
         assert!(result.generic_name.is_none());
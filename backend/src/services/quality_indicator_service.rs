@@ -0,0 +1,499 @@
+/*!
+ * GP Quality Indicator Service
+ *
+ * Computes the governance indicators required by the regional GP contract
+ * from existing clinical data:
+ *
+ * - `IPT01`: hypertensive patients (ICD-10 I10-I15) with a blood pressure
+ *   reading recorded in the period
+ * - `IPT02`: hypertensive patients whose latest reading in the period is
+ *   below 140/90 mmHg
+ * - `DIA01`: diabetic patients (ICD-10 E10-E14) with at least one visit in
+ *   the period
+ * - `VAC01`: patients aged 65+ with an influenza immunization (ICD-10 Z25.1)
+ *   recorded in the period
+ *
+ * Only active patients are counted. Patient dates of birth and visit vitals
+ * are encrypted, so they are decrypted in memory to compute ages and
+ * readings. Every run stores its criteria, population counts and results
+ * (no patient identifiers) in `quality_indicator_runs` as an audit trail.
+ */
+
+use anyhow::{Context, Result};
+use chrono::{Months, NaiveDate, Utc};
+use sqlx::{PgConnection, PgPool};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+use crate::db::rls::begin_with_rls;
+use crate::models::{
+    QualityIndicator, QualityIndicatorFilter, QualityIndicatorReport, ReportDateRange,
+};
+use crate::utils::EncryptionKey;
+
+/// Version of the computation rules, stored with every run
+pub const ALGORITHM_VERSION: &str = "2026.1";
+
+/// ICD-10 prefix pattern for hypertensive diseases
+const HYPERTENSION_PATTERN: &str = "^I1[0-5]";
+
+/// ICD-10 prefix pattern for diabetes mellitus
+const DIABETES_PATTERN: &str = "^E1[0-4]";
+
+/// ICD-10 codes recording an influenza immunization
+const FLU_VACCINATION_CODES: [&str; 2] = ["Z25.1", "Z251"];
+
+/// Minimum age for the influenza vaccination coverage indicator
+const VACCINATION_MIN_AGE: u32 = 65;
+
+/// Blood pressure control thresholds (mmHg, exclusive)
+const BP_SYSTOLIC_TARGET: f64 = 140.0;
+const BP_DIASTOLIC_TARGET: f64 = 90.0;
+
+/// A visit in the period, with its blood pressure reading if recorded
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct VisitObservation {
+    pub patient_id: Uuid,
+    pub visit_date: NaiveDate,
+    /// (systolic, diastolic)
+    pub blood_pressure: Option<(f64, f64)>,
+}
+
+/// Decrypted, de-duplicated data the indicators are computed from
+#[derive(Debug, Default)]
+pub(crate) struct IndicatorInputs {
+    pub active_patients: HashSet<Uuid>,
+    /// Age in completed years at the end of the period
+    pub ages: HashMap<Uuid, u32>,
+    pub hypertensive: HashSet<Uuid>,
+    pub diabetic: HashSet<Uuid>,
+    pub flu_vaccinated: HashSet<Uuid>,
+    pub visits: Vec<VisitObservation>,
+    /// Dates of birth or vitals that could not be decrypted or parsed
+    pub unreadable_records: i64,
+}
+
+impl IndicatorInputs {
+    /// Criteria and population counts stored with the run
+    fn summary(&self) -> serde_json::Value {
+        serde_json::json!({
+            "algorithm_version": ALGORITHM_VERSION,
+            "criteria": {
+                "hypertension_icd10": HYPERTENSION_PATTERN,
+                "diabetes_icd10": DIABETES_PATTERN,
+                "flu_vaccination_icd10": FLU_VACCINATION_CODES,
+                "vaccination_min_age": VACCINATION_MIN_AGE,
+                "bp_target_mmhg": format!("<{}/{}", BP_SYSTOLIC_TARGET, BP_DIASTOLIC_TARGET),
+                "patient_status": "ACTIVE",
+            },
+            "population": {
+                "active_patients": self.active_patients.len(),
+                "patients_with_known_age": self.ages.len(),
+                "hypertensive_patients": self.hypertensive.len(),
+                "diabetic_patients": self.diabetic.len(),
+                "visits_in_period": self.visits.len(),
+                "unreadable_records": self.unreadable_records,
+            },
+        })
+    }
+}
+
+/// Build an indicator, computing the percentage
+fn indicator(code: &str, description: &str, numerator: usize, denominator: usize) -> QualityIndicator {
+    let percentage = if denominator > 0 {
+        (numerator as f64 / denominator as f64 * 10000.0).round() / 100.0
+    } else {
+        0.0
+    };
+
+    QualityIndicator {
+        code: code.to_string(),
+        description: description.to_string(),
+        numerator: numerator as i64,
+        denominator: denominator as i64,
+        percentage,
+    }
+}
+
+/// Compute all indicators from the prepared inputs
+pub(crate) fn compute_indicators(inputs: &IndicatorInputs) -> Vec<QualityIndicator> {
+    let hypertensive: HashSet<Uuid> = inputs
+        .hypertensive
+        .intersection(&inputs.active_patients)
+        .copied()
+        .collect();
+    let diabetic: HashSet<Uuid> = inputs
+        .diabetic
+        .intersection(&inputs.active_patients)
+        .copied()
+        .collect();
+
+    // Latest blood pressure reading per patient in the period
+    let mut latest_bp: HashMap<Uuid, (NaiveDate, (f64, f64))> = HashMap::new();
+    for visit in &inputs.visits {
+        if let Some(bp) = visit.blood_pressure {
+            let entry = latest_bp.entry(visit.patient_id).or_insert((visit.visit_date, bp));
+            if visit.visit_date > entry.0 {
+                *entry = (visit.visit_date, bp);
+            }
+        }
+    }
+    let visited: HashSet<Uuid> = inputs.visits.iter().map(|v| v.patient_id).collect();
+
+    let bp_recorded = hypertensive.iter().filter(|p| latest_bp.contains_key(p)).count();
+    let bp_controlled = hypertensive
+        .iter()
+        .filter(|p| {
+            latest_bp.get(p).is_some_and(|(_, (sys, dia))| {
+                *sys < BP_SYSTOLIC_TARGET && *dia < BP_DIASTOLIC_TARGET
+            })
+        })
+        .count();
+    let diabetic_followed = diabetic.iter().filter(|p| visited.contains(p)).count();
+
+    let elderly: HashSet<Uuid> = inputs
+        .ages
+        .iter()
+        .filter(|(id, age)| **age >= VACCINATION_MIN_AGE && inputs.active_patients.contains(id))
+        .map(|(id, _)| *id)
+        .collect();
+    let elderly_vaccinated = elderly.intersection(&inputs.flu_vaccinated).count();
+
+    vec![
+        indicator(
+            "IPT01",
+            "Pazienti ipertesi con pressione arteriosa registrata nel periodo",
+            bp_recorded,
+            hypertensive.len(),
+        ),
+        indicator(
+            "IPT02",
+            "Pazienti ipertesi con ultima pressione arteriosa < 140/90 mmHg",
+            bp_controlled,
+            hypertensive.len(),
+        ),
+        indicator(
+            "DIA01",
+            "Pazienti diabetici con almeno una visita nel periodo",
+            diabetic_followed,
+            diabetic.len(),
+        ),
+        indicator(
+            "VAC01",
+            "Copertura vaccinale antinfluenzale nei pazienti con età >= 65 anni",
+            elderly_vaccinated,
+            elderly.len(),
+        ),
+    ]
+}
+
+/// Extract (systolic, diastolic) from decrypted vitals JSON
+pub(crate) fn blood_pressure_from_vitals(vitals: &serde_json::Value) -> Option<(f64, f64)> {
+    let systolic = vitals.get("blood_pressure_systolic")?.as_f64()?;
+    let diastolic = vitals.get("blood_pressure_diastolic")?.as_f64()?;
+    Some((systolic, diastolic))
+}
+
+/// Quality indicator service
+pub struct QualityIndicatorService {
+    pool: PgPool,
+    encryption_key: EncryptionKey,
+}
+
+impl QualityIndicatorService {
+    /// Create a new quality indicator service
+    pub fn new(pool: PgPool, encryption_key: EncryptionKey) -> Self {
+        Self {
+            pool,
+            encryption_key,
+        }
+    }
+
+    /// Resolve the measurement period (default: the 12 months up to today)
+    pub fn period(filter: &QualityIndicatorFilter) -> ReportDateRange {
+        let end_date = filter.end_date.unwrap_or_else(|| Utc::now().date_naive());
+        let start_date = filter.start_date.unwrap_or_else(|| {
+            end_date
+                .checked_sub_months(Months::new(12))
+                .unwrap_or(end_date)
+                + chrono::Duration::days(1)
+        });
+        ReportDateRange {
+            start_date,
+            end_date,
+        }
+    }
+
+    /// Compute the indicators and record the run
+    pub async fn generate(
+        &self,
+        filter: QualityIndicatorFilter,
+        user_id: Uuid,
+    ) -> Result<QualityIndicatorReport> {
+        let period = Self::period(&filter);
+
+        let mut tx = begin_with_rls(&self.pool, user_id)
+            .await
+            .context("Failed to begin transaction")?;
+
+        let inputs = self.load_inputs(&mut tx, &period).await?;
+        let indicators = compute_indicators(&inputs);
+        let summary = inputs.summary();
+
+        let (run_id, generated_at): (Uuid, chrono::DateTime<Utc>) = sqlx::query_as(
+            r#"
+            INSERT INTO quality_indicator_runs
+                (period_start, period_end, algorithm_version, inputs, results, generated_by)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id, created_at
+            "#,
+        )
+        .bind(period.start_date)
+        .bind(period.end_date)
+        .bind(ALGORITHM_VERSION)
+        .bind(&summary)
+        .bind(serde_json::to_value(&indicators)?)
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await
+        .context("Failed to record quality indicator run")?;
+
+        tx.commit().await.context("Failed to commit transaction")?;
+
+        Ok(QualityIndicatorReport {
+            run_id,
+            date_range: period,
+            generated_at,
+            indicators,
+            inputs: summary,
+        })
+    }
+
+    /// Load and decrypt the data needed for the indicators
+    async fn load_inputs(
+        &self,
+        tx: &mut PgConnection,
+        period: &ReportDateRange,
+    ) -> Result<IndicatorInputs> {
+        let mut inputs = IndicatorInputs::default();
+
+        let patients: Vec<(Uuid, String)> =
+            sqlx::query_as("SELECT id, date_of_birth FROM patients WHERE status = 'ACTIVE'")
+                .fetch_all(&mut *tx)
+                .await
+                .context("Failed to fetch patients")?;
+
+        for (patient_id, encrypted_dob) in patients {
+            inputs.active_patients.insert(patient_id);
+            let age = self
+                .encryption_key
+                .decrypt(&encrypted_dob)
+                .ok()
+                .and_then(|dob| NaiveDate::parse_from_str(dob.trim(), "%Y-%m-%d").ok())
+                .and_then(|dob| period.end_date.years_since(dob));
+            match age {
+                Some(age) => {
+                    inputs.ages.insert(patient_id, age);
+                }
+                None => inputs.unreadable_records += 1,
+            }
+        }
+
+        // Chronic conditions diagnosed up to the end of the period
+        let chronic: Vec<(Uuid, String)> = sqlx::query_as(
+            r#"
+            SELECT DISTINCT v.patient_id, UPPER(vd.icd10_code)
+            FROM visit_diagnoses vd
+            JOIN visits v ON vd.visit_id = v.id
+            WHERE v.visit_date <= $1
+              AND (UPPER(vd.icd10_code) ~ $2 OR UPPER(vd.icd10_code) ~ $3)
+            "#,
+        )
+        .bind(period.end_date)
+        .bind(HYPERTENSION_PATTERN)
+        .bind(DIABETES_PATTERN)
+        .fetch_all(&mut *tx)
+        .await
+        .context("Failed to fetch chronic diagnoses")?;
+
+        for (patient_id, code) in chronic {
+            if code.starts_with('I') {
+                inputs.hypertensive.insert(patient_id);
+            } else {
+                inputs.diabetic.insert(patient_id);
+            }
+        }
+
+        let vaccinated: Vec<Uuid> = sqlx::query_scalar(
+            r#"
+            SELECT DISTINCT v.patient_id
+            FROM visit_diagnoses vd
+            JOIN visits v ON vd.visit_id = v.id
+            WHERE v.visit_date BETWEEN $1 AND $2
+              AND UPPER(vd.icd10_code) = ANY($3)
+            "#,
+        )
+        .bind(period.start_date)
+        .bind(period.end_date)
+        .bind(&FLU_VACCINATION_CODES[..])
+        .fetch_all(&mut *tx)
+        .await
+        .context("Failed to fetch immunizations")?;
+        inputs.flu_vaccinated.extend(vaccinated);
+
+        // Visits of chronic patients in the period (only these need vitals)
+        let chronic_patients: Vec<Uuid> = inputs
+            .hypertensive
+            .union(&inputs.diabetic)
+            .copied()
+            .collect();
+        let visits: Vec<(Uuid, NaiveDate, Option<String>)> = sqlx::query_as(
+            r#"
+            SELECT patient_id, visit_date, vitals
+            FROM visits
+            WHERE visit_date BETWEEN $1 AND $2
+              AND patient_id = ANY($3)
+            "#,
+        )
+        .bind(period.start_date)
+        .bind(period.end_date)
+        .bind(&chronic_patients)
+        .fetch_all(&mut *tx)
+        .await
+        .context("Failed to fetch visits")?;
+
+        for (patient_id, visit_date, encrypted_vitals) in visits {
+            let blood_pressure = match encrypted_vitals {
+                Some(encrypted) => {
+                    match self.encryption_key.decrypt_json::<serde_json::Value>(&encrypted) {
+                        Ok(vitals) => blood_pressure_from_vitals(&vitals),
+                        Err(_) => {
+                            inputs.unreadable_records += 1;
+                            None
+                        }
+                    }
+                }
+                None => None,
+            };
+            inputs.visits.push(VisitObservation {
+                patient_id,
+                visit_date,
+                blood_pressure,
+            });
+        }
+
+        Ok(inputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn visit(patient_id: Uuid, visit_date: NaiveDate, bp: Option<(f64, f64)>) -> VisitObservation {
+        VisitObservation {
+            patient_id,
+            visit_date,
+            blood_pressure: bp,
+        }
+    }
+
+    fn find<'a>(indicators: &'a [QualityIndicator], code: &str) -> &'a QualityIndicator {
+        indicators.iter().find(|i| i.code == code).unwrap()
+    }
+
+    #[test]
+    fn test_hypertension_indicators_use_latest_reading() {
+        let (a, b, c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let mut inputs = IndicatorInputs::default();
+        inputs.active_patients.extend([a, b, c]);
+        inputs.hypertensive.extend([a, b, c]);
+        inputs.visits = vec![
+            // a: uncontrolled first, controlled at the latest visit
+            visit(a, date(2026, 1, 10), Some((160.0, 95.0))),
+            visit(a, date(2026, 5, 10), Some((130.0, 80.0))),
+            // b: latest reading uncontrolled
+            visit(b, date(2026, 2, 1), Some((150.0, 85.0))),
+            // c: visit without a reading
+            visit(c, date(2026, 3, 1), None),
+        ];
+
+        let indicators = compute_indicators(&inputs);
+
+        let recorded = find(&indicators, "IPT01");
+        assert_eq!((recorded.numerator, recorded.denominator), (2, 3));
+        assert_eq!(recorded.percentage, 66.67);
+
+        let controlled = find(&indicators, "IPT02");
+        assert_eq!((controlled.numerator, controlled.denominator), (1, 3));
+    }
+
+    #[test]
+    fn test_inactive_patients_are_excluded() {
+        let (active, inactive) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut inputs = IndicatorInputs::default();
+        inputs.active_patients.insert(active);
+        inputs.diabetic.extend([active, inactive]);
+        inputs.visits = vec![visit(inactive, date(2026, 1, 1), None)];
+
+        let diabetes = find(&compute_indicators(&inputs), "DIA01");
+        assert_eq!((diabetes.numerator, diabetes.denominator), (0, 1));
+    }
+
+    #[test]
+    fn test_vaccination_coverage_counts_patients_65_and_over() {
+        let (old_vax, old, young_vax) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let mut inputs = IndicatorInputs::default();
+        inputs.active_patients.extend([old_vax, old, young_vax]);
+        inputs.ages.extend([(old_vax, 70), (old, 65), (young_vax, 40)]);
+        inputs.flu_vaccinated.extend([old_vax, young_vax]);
+
+        let coverage = find(&compute_indicators(&inputs), "VAC01");
+        assert_eq!((coverage.numerator, coverage.denominator), (1, 2));
+        assert_eq!(coverage.percentage, 50.0);
+    }
+
+    #[test]
+    fn test_empty_denominator_gives_zero_percentage() {
+        let indicators = compute_indicators(&IndicatorInputs::default());
+        assert_eq!(indicators.len(), 4);
+        assert!(indicators.iter().all(|i| i.denominator == 0 && i.percentage == 0.0));
+    }
+
+    #[test]
+    fn test_blood_pressure_from_vitals() {
+        let vitals = serde_json::json!({
+            "blood_pressure_systolic": 128,
+            "blood_pressure_diastolic": 82.5,
+            "heart_rate": 70,
+        });
+        assert_eq!(blood_pressure_from_vitals(&vitals), Some((128.0, 82.5)));
+        assert_eq!(blood_pressure_from_vitals(&serde_json::json!({"heart_rate": 70})), None);
+    }
+
+    #[test]
+    fn test_default_period_is_twelve_months() {
+        let filter = QualityIndicatorFilter {
+            start_date: None,
+            end_date: Some(date(2026, 12, 31)),
+        };
+        let period = QualityIndicatorService::period(&filter);
+        assert_eq!(period.start_date, date(2026, 1, 1));
+        assert_eq!(period.end_date, date(2026, 12, 31));
+    }
+
+    #[test]
+    fn test_summary_has_no_patient_identifiers() {
+        let patient = Uuid::new_v4();
+        let mut inputs = IndicatorInputs::default();
+        inputs.active_patients.insert(patient);
+
+        let summary = inputs.summary().to_string();
+        assert!(!summary.contains(&patient.to_string()));
+        assert!(summary.contains(ALGORITHM_VERSION));
+    }
+}
@@ -0,0 +1,207 @@
+/*!
+ * Rate Limit Quota Service
+ *
+ * Stores per-user quotas and keeps the quotas of the rate limiter in sync
+ * with them. Quotas set through the admin API apply at once on the instance
+ * that handled the request; the refresh task picks them up on the others.
+ */
+
+use chrono::{TimeZone, Utc};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use tokio::time::{sleep, Duration as TokioDuration};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::middleware::rate_limit::{
+    user_client_key, RateLimitLayer, RateLimitTier, RATE_LIMIT_WINDOW_SECS,
+};
+use crate::models::{
+    RateLimitDefaults, RateLimitOverview, RateLimitQuota, SetRateLimitQuotaRequest,
+    UserRateLimitStatus,
+};
+use crate::utils::{AppError, Result};
+
+/// Seconds between two reloads of the quotas
+const QUOTA_REFRESH_SECS: u64 = 60;
+
+const QUOTA_COLUMNS: &str = "user_id, requests_per_minute, bulk_requests_per_minute, reason, \
+                             created_at, updated_at, updated_by";
+
+/// Rate limit quota service
+pub struct RateLimitQuotaService {
+    pool: PgPool,
+}
+
+impl RateLimitQuotaService {
+    /// Create a new rate limit quota service
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// List all quotas
+    pub async fn list(&self) -> Result<Vec<RateLimitQuota>> {
+        let quotas = sqlx::query_as::<_, RateLimitQuota>(&format!(
+            "SELECT {} FROM rate_limit_quotas ORDER BY updated_at DESC",
+            QUOTA_COLUMNS
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(quotas)
+    }
+
+    /// Get the quota of a user, if any
+    pub async fn get(&self, user_id: Uuid) -> Result<Option<RateLimitQuota>> {
+        let quota = sqlx::query_as::<_, RateLimitQuota>(&format!(
+            "SELECT {} FROM rate_limit_quotas WHERE user_id = $1",
+            QUOTA_COLUMNS
+        ))
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(quota)
+    }
+
+    /// Create or replace the quota of a user
+    pub async fn set(
+        &self,
+        user_id: Uuid,
+        req: &SetRateLimitQuotaRequest,
+        updated_by: Uuid,
+    ) -> Result<RateLimitQuota> {
+        let user_exists: bool =
+            sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM users WHERE id = $1)")
+                .bind(user_id)
+                .fetch_one(&self.pool)
+                .await?;
+        if !user_exists {
+            return Err(AppError::NotFound("User not found".to_string()));
+        }
+
+        let quota = sqlx::query_as::<_, RateLimitQuota>(&format!(
+            r#"
+            INSERT INTO rate_limit_quotas
+                (user_id, requests_per_minute, bulk_requests_per_minute, reason, updated_by)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (user_id) DO UPDATE
+            SET requests_per_minute = EXCLUDED.requests_per_minute,
+                bulk_requests_per_minute = EXCLUDED.bulk_requests_per_minute,
+                reason = EXCLUDED.reason,
+                updated_at = NOW(),
+                updated_by = EXCLUDED.updated_by
+            RETURNING {}
+            "#,
+            QUOTA_COLUMNS
+        ))
+        .bind(user_id)
+        .bind(req.requests_per_minute)
+        .bind(req.bulk_requests_per_minute)
+        .bind(&req.reason)
+        .bind(updated_by)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(quota)
+    }
+
+    /// Remove the quota of a user, returning whether there was one
+    pub async fn delete(&self, user_id: Uuid) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM rate_limit_quotas WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Replace the quotas of the rate limiter with the stored ones
+    pub async fn load_into(&self, limiter: &RateLimitLayer) -> Result<usize> {
+        let quotas: HashMap<_, _> = self
+            .list()
+            .await?
+            .into_iter()
+            .map(|quota| (quota.user_id, quota.limits()))
+            .collect();
+        let count = quotas.len();
+
+        limiter.replace_quotas(quotas);
+        Ok(count)
+    }
+
+    /// Default limits and every quota, with current usage
+    pub async fn overview(&self, limiter: &RateLimitLayer) -> Result<RateLimitOverview> {
+        let config = limiter.config();
+        let mut quotas = Vec::new();
+        for quota in self.list().await? {
+            quotas.push(status(limiter, quota.user_id, Some(quota)).await);
+        }
+
+        Ok(RateLimitOverview {
+            defaults: RateLimitDefaults {
+                unauthenticated_per_minute: config.unauthenticated_limit,
+                authenticated_per_minute: config.authenticated_limit,
+                bulk_per_minute: config.bulk_limit,
+            },
+            quotas,
+        })
+    }
+
+    /// Limits and current usage of a user
+    pub async fn user_status(
+        &self,
+        limiter: &RateLimitLayer,
+        user_id: Uuid,
+    ) -> Result<UserRateLimitStatus> {
+        let quota = self.get(user_id).await?;
+        Ok(status(limiter, user_id, quota).await)
+    }
+}
+
+/// Limits and current usage of a user
+async fn status(
+    limiter: &RateLimitLayer,
+    user_id: Uuid,
+    quota: Option<RateLimitQuota>,
+) -> UserRateLimitStatus {
+    let client = user_client_key(user_id);
+    let now = Utc::now().timestamp().max(0) as u64;
+    let resets_at = (now / RATE_LIMIT_WINDOW_SECS + 1) * RATE_LIMIT_WINDOW_SECS;
+
+    UserRateLimitStatus {
+        user_id,
+        quota,
+        requests_per_minute: limiter.limit_for(RateLimitTier::Authenticated, Some(user_id)),
+        bulk_requests_per_minute: limiter.limit_for(RateLimitTier::Bulk, Some(user_id)),
+        requests_used: limiter
+            .usage(&RateLimitTier::Authenticated.key(&client))
+            .await,
+        bulk_requests_used: limiter.usage(&RateLimitTier::Bulk.key(&client)).await,
+        window_resets_at: Utc
+            .timestamp_opt(resets_at as i64, 0)
+            .single()
+            .unwrap_or_else(Utc::now),
+    }
+}
+
+/// Spawn the task that reloads the quotas of the rate limiter
+///
+/// Loads them once at startup, then every minute, so quotas changed on
+/// another instance apply here too. Also drops the counters of past windows.
+pub fn spawn_rate_limit_quota_refresh(pool: PgPool, limiter: RateLimitLayer) {
+    let service = RateLimitQuotaService::new(pool);
+
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = service.load_into(&limiter).await {
+                warn!("Failed to load rate limit quotas: {}", e);
+            }
+            if let Err(e) = limiter.prune().await {
+                warn!("Failed to prune rate limit counters: {:#}", e);
+            }
+
+            sleep(TokioDuration::from_secs(QUOTA_REFRESH_SECS)).await;
+        }
+    });
+}
@@ -0,0 +1,966 @@
+/*!
+ * Recall Service
+ *
+ * Manages recall rules and runs them. A run selects the rule's population
+ * with PatientCohortService, as the rule owner so RLS applies. It then
+ * drops the patients who had an order or result for the rule's lab tests
+ * recently enough, and updates the recall list:
+ * - Open entries of patients no longer overdue are resolved.
+ * - New overdue patients get an open entry.
+ * - Open entries due for a reminder are queued a FOLLOW_UP_REMINDER.
+ * - Patients who opted out are left alone.
+ *
+ * A background job runs due rules every few minutes. Due rules are claimed
+ * (next run rescheduled) in a FOR UPDATE SKIP LOCKED transaction, as for
+ * report subscriptions. Every run is recorded in `recall_runs` and in the
+ * audit log.
+ */
+
+use crate::db::rls::begin_with_rls;
+use crate::models::{
+    AuditAction, AuditLog, CreateAuditLog, CreateRecallOptOutRequest, CreateRecallRuleRequest,
+    EntityType, RecallEntry, RecallEntryFilter, RecallEntryResponse, RecallEntryStatus,
+    RecallOptOut, RecallOptOutFilter, RecallOutcome, RecallRule, RecallRuleResponse, RecallRun,
+    UpdateRecallRuleRequest,
+};
+use crate::services::patient_cohort_service::SelectedPatient;
+use crate::services::{EmailService, NotificationService, PatientCohortService};
+use crate::utils::{AppError, EncryptionKey, Result};
+use chrono::{Duration, NaiveTime, Utc};
+use sqlx::PgPool;
+use std::collections::HashSet;
+use tokio::time::{sleep, Duration as TokioDuration};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// Seconds between checks for due rules
+const CHECK_INTERVAL_SECS: u64 = 300;
+
+/// Rules claimed per check
+const CLAIM_BATCH_SIZE: i64 = 10;
+
+/// Runs listed per rule
+const MAX_RUNS_LISTED: i64 = 50;
+
+const RULE_COLUMNS: &str = r#"
+    id, name, description, criteria, subject, message_body, cadence, reminder_interval_days,
+    max_reminders, is_active, next_run_at, last_run_at, created_by, created_at, updated_at
+"#;
+
+const RUN_COLUMNS: &str = r#"
+    id, rule_id, triggered_by, run_at, overdue, new_entries, reminded, skipped, opted_out,
+    resolved, unreadable_records, error
+"#;
+
+const ENTRY_COLUMNS: &str = r#"
+    e.id, e.rule_id, e.patient_id, e.status, e.reminders_sent, e.last_reminded_at,
+    e.last_notification_id, e.last_skip_reason, e.opened_at, e.closed_at
+"#;
+
+const OPT_OUT_COLUMNS: &str = "o.id, o.patient_id, o.rule_id, o.reason, o.created_by, o.created_at";
+
+/// Recall entry with the patient's medical record number and encrypted name
+#[derive(sqlx::FromRow)]
+struct EntryRow {
+    #[sqlx(flatten)]
+    entry: RecallEntry,
+    medical_record_number: String,
+    first_name: String,
+    last_name: String,
+}
+
+/// Entry counts by status, reminders sent, exhausted entries and the mean
+/// days to resolution
+type OutcomeRow = (i64, i64, i64, i64, i64, i64, i64, Option<f64>);
+
+/// Counts of a run
+#[derive(Default)]
+struct RunCounts {
+    overdue: usize,
+    new_entries: usize,
+    reminded: usize,
+    skipped: usize,
+    opted_out: usize,
+    resolved: usize,
+    unreadable: usize,
+}
+
+/// Recall service
+pub struct RecallService {
+    pool: PgPool,
+    encryption_key: EncryptionKey,
+}
+
+impl RecallService {
+    /// Create a new recall service
+    pub fn new(pool: PgPool, encryption_key: EncryptionKey) -> Self {
+        Self {
+            pool,
+            encryption_key,
+        }
+    }
+
+    // ==================== Rules ====================
+
+    /// List rules: every one for administrators, otherwise the caller's own
+    pub async fn list(&self, user_id: Uuid, is_admin: bool) -> Result<Vec<RecallRuleResponse>> {
+        let rows = sqlx::query_as::<_, RecallRule>(&format!(
+            "SELECT {} FROM recall_rules WHERE $1 OR created_by = $2 ORDER BY name, created_at",
+            RULE_COLUMNS
+        ))
+        .bind(is_admin)
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(to_response).collect()
+    }
+
+    /// Get a rule the caller may see
+    pub async fn get(
+        &self,
+        rule_id: Uuid,
+        user_id: Uuid,
+        is_admin: bool,
+    ) -> Result<RecallRuleResponse> {
+        to_response(self.get_row(rule_id, user_id, is_admin).await?)
+    }
+
+    /// Create a rule owned by `created_by`; the first run is at the start
+    /// of the next period
+    pub async fn create(
+        &self,
+        req: &CreateRecallRuleRequest,
+        created_by: Uuid,
+    ) -> Result<RecallRuleResponse> {
+        let row = sqlx::query_as::<_, RecallRule>(&format!(
+            r#"
+            INSERT INTO recall_rules (
+                name, description, criteria, subject, message_body, cadence,
+                reminder_interval_days, max_reminders, next_run_at, created_by
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            RETURNING {}
+            "#,
+            RULE_COLUMNS
+        ))
+        .bind(req.name.trim())
+        .bind(&req.description)
+        .bind(serde_json::to_value(&req.criteria).unwrap_or_default())
+        .bind(req.subject.trim())
+        .bind(&req.message_body)
+        .bind(req.cadence.as_str())
+        .bind(req.reminder_interval_days)
+        .bind(req.max_reminders)
+        .bind(req.cadence.next_run_after(Utc::now()))
+        .bind(created_by)
+        .fetch_one(&self.pool)
+        .await?;
+
+        to_response(row)
+    }
+
+    /// Update a rule the caller may see
+    pub async fn update(
+        &self,
+        rule_id: Uuid,
+        req: &UpdateRecallRuleRequest,
+        user_id: Uuid,
+        is_admin: bool,
+    ) -> Result<RecallRuleResponse> {
+        let current = to_response(self.get_row(rule_id, user_id, is_admin).await?)?;
+
+        let criteria = req
+            .criteria
+            .as_ref()
+            .map(|criteria| serde_json::to_value(criteria).unwrap_or_default());
+        // As for report subscriptions, a paused rule does not catch up on
+        // missed runs
+        let reactivated = req.is_active == Some(true) && !current.is_active;
+        let next_run_at = (req.cadence.is_some() || reactivated).then(|| {
+            req.cadence
+                .unwrap_or(current.cadence)
+                .next_run_after(Utc::now())
+        });
+
+        let row = sqlx::query_as::<_, RecallRule>(&format!(
+            r#"
+            UPDATE recall_rules
+            SET name = COALESCE($2, name),
+                description = COALESCE($3, description),
+                criteria = COALESCE($4, criteria),
+                subject = COALESCE($5, subject),
+                message_body = COALESCE($6, message_body),
+                cadence = COALESCE($7, cadence),
+                next_run_at = COALESCE($8, next_run_at),
+                reminder_interval_days = COALESCE($9, reminder_interval_days),
+                max_reminders = COALESCE($10, max_reminders),
+                is_active = COALESCE($11, is_active),
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING {}
+            "#,
+            RULE_COLUMNS
+        ))
+        .bind(rule_id)
+        .bind(req.name.as_deref().map(str::trim))
+        .bind(&req.description)
+        .bind(criteria)
+        .bind(req.subject.as_deref().map(str::trim))
+        .bind(&req.message_body)
+        .bind(req.cadence.map(|cadence| cadence.as_str()))
+        .bind(next_run_at)
+        .bind(req.reminder_interval_days)
+        .bind(req.max_reminders)
+        .bind(req.is_active)
+        .fetch_one(&self.pool)
+        .await?;
+
+        to_response(row)
+    }
+
+    /// Delete a rule the caller may see, with its recall list and runs
+    pub async fn delete(&self, rule_id: Uuid, user_id: Uuid, is_admin: bool) -> Result<()> {
+        self.get_row(rule_id, user_id, is_admin).await?;
+
+        sqlx::query("DELETE FROM recall_rules WHERE id = $1")
+            .bind(rule_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_row(&self, rule_id: Uuid, user_id: Uuid, is_admin: bool) -> Result<RecallRule> {
+        sqlx::query_as::<_, RecallRule>(&format!(
+            "SELECT {} FROM recall_rules WHERE id = $1 AND ($2 OR created_by = $3)",
+            RULE_COLUMNS
+        ))
+        .bind(rule_id)
+        .bind(is_admin)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Recall rule {} not found", rule_id)))
+    }
+
+    // ==================== Recall Lists ====================
+
+    /// Entries of a rule's recall list, newest first, limited to the
+    /// patients the caller may see
+    pub async fn entries(
+        &self,
+        rule_id: Uuid,
+        filter: &RecallEntryFilter,
+        user_id: Uuid,
+        is_admin: bool,
+    ) -> Result<Vec<RecallEntryResponse>> {
+        self.get_row(rule_id, user_id, is_admin).await?;
+
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        let rows = sqlx::query_as::<_, EntryRow>(&format!(
+            r#"
+            SELECT {}, p.medical_record_number, p.first_name, p.last_name
+            FROM recall_entries e
+            JOIN patients p ON p.id = e.patient_id
+            WHERE e.rule_id = $1 AND ($2::TEXT IS NULL OR e.status = $2)
+            ORDER BY e.opened_at DESC
+            "#,
+            ENTRY_COLUMNS
+        ))
+        .bind(rule_id)
+        .bind(filter.status.map(|status| status.as_str()))
+        .fetch_all(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        rows.into_iter()
+            .map(|row| self.entry_response(row))
+            .collect()
+    }
+
+    /// Close an open entry by hand (e.g. the patient is followed elsewhere)
+    pub async fn close_entry(
+        &self,
+        entry_id: Uuid,
+        user_id: Uuid,
+        is_admin: bool,
+    ) -> Result<RecallEntryResponse> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        let row = sqlx::query_as::<_, EntryRow>(&format!(
+            r#"
+            WITH closed AS (
+                UPDATE recall_entries e
+                SET status = 'CLOSED', closed_at = NOW()
+                FROM recall_rules r
+                WHERE e.id = $1 AND e.status = 'OPEN'
+                  AND r.id = e.rule_id AND ($2 OR r.created_by = $3)
+                RETURNING e.*
+            )
+            SELECT {}, p.medical_record_number, p.first_name, p.last_name
+            FROM closed e
+            JOIN patients p ON p.id = e.patient_id
+            "#,
+            ENTRY_COLUMNS
+        ))
+        .bind(entry_id)
+        .bind(is_admin)
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Open recall entry {} not found", entry_id)))?;
+
+        tx.commit().await?;
+
+        self.entry_response(row)
+    }
+
+    /// Outcome of a rule's campaign so far
+    pub async fn outcome(
+        &self,
+        rule_id: Uuid,
+        user_id: Uuid,
+        is_admin: bool,
+    ) -> Result<RecallOutcome> {
+        let rule = self.get_row(rule_id, user_id, is_admin).await?;
+
+        let (
+            total_entries,
+            open,
+            resolved,
+            opted_out,
+            closed,
+            reminders_sent,
+            exhausted,
+            average_days,
+        ): OutcomeRow = sqlx::query_as(
+            r#"
+            SELECT
+                COUNT(*),
+                COUNT(*) FILTER (WHERE status = 'OPEN'),
+                COUNT(*) FILTER (WHERE status = 'RESOLVED'),
+                COUNT(*) FILTER (WHERE status = 'OPTED_OUT'),
+                COUNT(*) FILTER (WHERE status = 'CLOSED'),
+                COALESCE(SUM(reminders_sent), 0)::BIGINT,
+                COUNT(*) FILTER (WHERE status = 'OPEN' AND reminders_sent >= $2),
+                (AVG(EXTRACT(EPOCH FROM closed_at - opened_at) / 86400)
+                    FILTER (WHERE status = 'RESOLVED'))::FLOAT8
+            FROM recall_entries
+            WHERE rule_id = $1
+            "#,
+        )
+        .bind(rule_id)
+        .bind(rule.max_reminders)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(RecallOutcome {
+            rule_id,
+            total_entries,
+            open,
+            resolved,
+            opted_out,
+            closed,
+            reminders_sent,
+            exhausted,
+            resolution_rate: RecallOutcome::compute_resolution_rate(resolved, total_entries - open),
+            average_days_to_resolution: average_days.map(|days| (days * 10.0).round() / 10.0),
+        })
+    }
+
+    /// Latest runs of a rule
+    pub async fn runs(
+        &self,
+        rule_id: Uuid,
+        user_id: Uuid,
+        is_admin: bool,
+    ) -> Result<Vec<RecallRun>> {
+        self.get_row(rule_id, user_id, is_admin).await?;
+
+        let runs = sqlx::query_as::<_, RecallRun>(&format!(
+            "SELECT {} FROM recall_runs WHERE rule_id = $1 ORDER BY run_at DESC LIMIT $2",
+            RUN_COLUMNS
+        ))
+        .bind(rule_id)
+        .bind(MAX_RUNS_LISTED)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(runs)
+    }
+
+    fn entry_response(&self, row: EntryRow) -> Result<RecallEntryResponse> {
+        let entry = row.entry;
+        let status = RecallEntryStatus::from_str(&entry.status).ok_or_else(|| {
+            AppError::Internal(format!("Unknown recall entry status: {}", entry.status))
+        })?;
+        let patient_name = match (
+            self.encryption_key.decrypt(&row.first_name),
+            self.encryption_key.decrypt(&row.last_name),
+        ) {
+            (Ok(first_name), Ok(last_name)) => Some(format!("{} {}", first_name, last_name)),
+            _ => None,
+        };
+
+        Ok(RecallEntryResponse {
+            id: entry.id,
+            rule_id: entry.rule_id,
+            patient_id: entry.patient_id,
+            medical_record_number: row.medical_record_number,
+            patient_name,
+            status,
+            reminders_sent: entry.reminders_sent,
+            last_reminded_at: entry.last_reminded_at,
+            last_notification_id: entry.last_notification_id,
+            last_skip_reason: entry.last_skip_reason,
+            opened_at: entry.opened_at,
+            closed_at: entry.closed_at,
+        })
+    }
+
+    // ==================== Opt-outs ====================
+
+    /// Opt-outs of the patients the caller may see
+    pub async fn list_opt_outs(
+        &self,
+        filter: &RecallOptOutFilter,
+        user_id: Uuid,
+    ) -> Result<Vec<RecallOptOut>> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        let opt_outs = sqlx::query_as::<_, RecallOptOut>(&format!(
+            r#"
+            SELECT {}
+            FROM recall_opt_outs o
+            JOIN patients p ON p.id = o.patient_id
+            WHERE $1::UUID IS NULL OR o.patient_id = $1
+            ORDER BY o.created_at DESC
+            "#,
+            OPT_OUT_COLUMNS
+        ))
+        .bind(filter.patient_id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(opt_outs)
+    }
+
+    /// Opt a patient out of one rule or of every recall; their open entries
+    /// are marked OPTED_OUT
+    pub async fn create_opt_out(
+        &self,
+        req: &CreateRecallOptOutRequest,
+        user_id: Uuid,
+    ) -> Result<RecallOptOut> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        let patient_exists: bool =
+            sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM patients WHERE id = $1)")
+                .bind(req.patient_id)
+                .fetch_one(&mut *tx)
+                .await?;
+        if !patient_exists {
+            return Err(AppError::NotFound(format!(
+                "Patient {} not found",
+                req.patient_id
+            )));
+        }
+        if let Some(rule_id) = req.rule_id {
+            let rule_exists: bool =
+                sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM recall_rules WHERE id = $1)")
+                    .bind(rule_id)
+                    .fetch_one(&mut *tx)
+                    .await?;
+            if !rule_exists {
+                return Err(AppError::NotFound(format!(
+                    "Recall rule {} not found",
+                    rule_id
+                )));
+            }
+        }
+
+        let opt_out = sqlx::query_as::<_, RecallOptOut>(&format!(
+            r#"
+            INSERT INTO recall_opt_outs AS o (patient_id, rule_id, reason, created_by)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT DO NOTHING
+            RETURNING {}
+            "#,
+            OPT_OUT_COLUMNS
+        ))
+        .bind(req.patient_id)
+        .bind(req.rule_id)
+        .bind(&req.reason)
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| {
+            AppError::Conflict("The patient has already opted out of this recall".to_string())
+        })?;
+
+        sqlx::query(
+            r#"
+            UPDATE recall_entries
+            SET status = 'OPTED_OUT', closed_at = NOW()
+            WHERE patient_id = $1 AND status = 'OPEN' AND ($2::UUID IS NULL OR rule_id = $2)
+            "#,
+        )
+        .bind(req.patient_id)
+        .bind(req.rule_id)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(opt_out)
+    }
+
+    /// Remove an opt-out of a patient the caller may see; the patient is
+    /// picked up again by the next run
+    pub async fn delete_opt_out(&self, opt_out_id: Uuid, user_id: Uuid) -> Result<RecallOptOut> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        let opt_out = sqlx::query_as::<_, RecallOptOut>(&format!(
+            r#"
+            DELETE FROM recall_opt_outs o
+            USING patients p
+            WHERE o.id = $1 AND p.id = o.patient_id
+            RETURNING {}
+            "#,
+            OPT_OUT_COLUMNS
+        ))
+        .bind(opt_out_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Recall opt-out {} not found", opt_out_id)))?;
+
+        tx.commit().await?;
+        Ok(opt_out)
+    }
+
+    // ==================== Runs ====================
+
+    /// Run a rule now, without changing its schedule
+    pub async fn run_now(
+        &self,
+        rule_id: Uuid,
+        user_id: Uuid,
+        is_admin: bool,
+        notification_service: &NotificationService,
+    ) -> Result<RecallRun> {
+        let rule = to_response(self.get_row(rule_id, user_id, is_admin).await?)?;
+        self.run(&rule, notification_service, Some(user_id)).await
+    }
+
+    /// Run every due rule, returning how many were run by this instance
+    pub async fn run_due(&self, notification_service: &NotificationService) -> Result<usize> {
+        let due = self.claim_due().await?;
+        let count = due.len();
+
+        for rule in due {
+            if let Err(e) = self.run(&rule, notification_service, None).await {
+                error!("Recall rule {} run failed: {}", rule.id, e);
+            }
+        }
+
+        Ok(count)
+    }
+
+    /// Claim due rules: their next run is rescheduled before they run, so no
+    /// other instance runs them too
+    async fn claim_due(&self) -> Result<Vec<RecallRuleResponse>> {
+        let mut tx = self.pool.begin().await?;
+
+        let due = sqlx::query_as::<_, RecallRule>(&format!(
+            r#"
+            SELECT {} FROM recall_rules
+            WHERE is_active AND next_run_at <= NOW()
+            ORDER BY next_run_at
+            LIMIT $1
+            FOR UPDATE SKIP LOCKED
+            "#,
+            RULE_COLUMNS
+        ))
+        .bind(CLAIM_BATCH_SIZE)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let now = Utc::now();
+        let mut claimed = Vec::with_capacity(due.len());
+        for rule in due {
+            let rule = to_response(rule)?;
+            sqlx::query("UPDATE recall_rules SET next_run_at = $2 WHERE id = $1")
+                .bind(rule.id)
+                .bind(rule.cadence.next_run_after(now))
+                .execute(&mut *tx)
+                .await?;
+            claimed.push(rule);
+        }
+
+        tx.commit().await?;
+        Ok(claimed)
+    }
+
+    /// Run a rule and record the run; a failed evaluation is recorded with
+    /// its error
+    async fn run(
+        &self,
+        rule: &RecallRuleResponse,
+        notification_service: &NotificationService,
+        triggered_by: Option<Uuid>,
+    ) -> Result<RecallRun> {
+        let (counts, run_error) = match self.evaluate(rule, notification_service).await {
+            Ok(counts) => (counts, None),
+            Err(e) => {
+                warn!("Recall rule {} run failed: {}", rule.id, e);
+                (RunCounts::default(), Some(e.to_string()))
+            }
+        };
+
+        let run = sqlx::query_as::<_, RecallRun>(&format!(
+            r#"
+            INSERT INTO recall_runs (
+                rule_id, triggered_by, overdue, new_entries, reminded, skipped, opted_out,
+                resolved, unreadable_records, error
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            RETURNING {}
+            "#,
+            RUN_COLUMNS
+        ))
+        .bind(rule.id)
+        .bind(triggered_by)
+        .bind(counts.overdue as i32)
+        .bind(counts.new_entries as i32)
+        .bind(counts.reminded as i32)
+        .bind(counts.skipped as i32)
+        .bind(counts.opted_out as i32)
+        .bind(counts.resolved as i32)
+        .bind(counts.unreadable as i32)
+        .bind(&run_error)
+        .fetch_one(&self.pool)
+        .await?;
+
+        sqlx::query("UPDATE recall_rules SET last_run_at = $2 WHERE id = $1")
+            .bind(rule.id)
+            .bind(run.run_at)
+            .execute(&self.pool)
+            .await?;
+
+        let _ = AuditLog::create(
+            &self.pool,
+            CreateAuditLog {
+                user_id: Some(triggered_by.unwrap_or(rule.created_by)),
+                action: AuditAction::Create,
+                entity_type: EntityType::RecallRule,
+                entity_id: Some(rule.id.to_string()),
+                changes: Some(serde_json::json!({
+                    "type": "recall_run",
+                    "run_id": run.id,
+                    "overdue": run.overdue,
+                    "new_entries": run.new_entries,
+                    "reminded": run.reminded,
+                    "resolved": run.resolved,
+                    "error": run.error,
+                })),
+                ip_address: None,
+                user_agent: None,
+                request_id: None,
+            },
+        )
+        .await;
+
+        Ok(run)
+    }
+
+    /// Update the recall list of a rule and queue the due reminders
+    async fn evaluate(
+        &self,
+        rule: &RecallRuleResponse,
+        notification_service: &NotificationService,
+    ) -> Result<RunCounts> {
+        // Runs use the owner's permissions, which must still allow recalls
+        let owner: Option<(String, bool)> =
+            sqlx::query_as("SELECT role::TEXT, is_active FROM users WHERE id = $1")
+                .bind(rule.created_by)
+                .fetch_optional(&self.pool)
+                .await?;
+        match owner {
+            Some((role, true)) if role == "ADMIN" || role == "DOCTOR" => {}
+            _ => {
+                return Err(AppError::Forbidden(
+                    "Rule owner can no longer run recalls".to_string(),
+                ))
+            }
+        }
+
+        let today = Utc::now().date_naive();
+        let selection = PatientCohortService::new(self.pool.clone(), self.encryption_key.clone())
+            .select(&rule.criteria.cohort_criteria(today), rule.created_by)
+            .await?;
+        let candidate_ids: Vec<Uuid> = selection
+            .patients
+            .iter()
+            .map(|patient| patient.member.patient_id)
+            .collect();
+
+        let mut tx = begin_with_rls(&self.pool, rule.created_by).await?;
+
+        let up_to_date: HashSet<Uuid> = match rule.criteria.lab_cutoff(today) {
+            Some(cutoff) => sqlx::query_scalar(
+                r#"
+                SELECT o.patient_id
+                FROM lab_orders o
+                JOIN lab_order_tests t ON t.order_id = o.id
+                WHERE o.patient_id = ANY($1)
+                  AND o.deleted_at IS NULL
+                  AND o.status <> 'CANCELLED'
+                  AND t.loinc_code = ANY($2)
+                  AND o.ordered_at >= $3
+                UNION
+                SELECT r.patient_id
+                FROM lab_results r
+                WHERE r.patient_id = ANY($1)
+                  AND r.deleted_at IS NULL
+                  AND r.loinc_code = ANY($2)
+                  AND r.collected_at >= $3
+                "#,
+            )
+            .bind(&candidate_ids)
+            .bind(&rule.criteria.lab_loinc_codes)
+            .bind(cutoff.and_time(NaiveTime::MIN).and_utc())
+            .fetch_all(&mut *tx)
+            .await?
+            .into_iter()
+            .collect(),
+            None => HashSet::new(),
+        };
+
+        let overdue: Vec<SelectedPatient> = selection
+            .patients
+            .into_iter()
+            .filter(|patient| !up_to_date.contains(&patient.member.patient_id))
+            .collect();
+        let overdue_ids: Vec<Uuid> = overdue
+            .iter()
+            .map(|patient| patient.member.patient_id)
+            .collect();
+
+        // Open entries of patients no longer overdue are resolved, or closed
+        // when the patient is no longer active. Unreadable patients are kept
+        // as they are, since whether they are overdue is unknown.
+        let finished: Vec<String> = sqlx::query_scalar(
+            r#"
+            UPDATE recall_entries e
+            SET status = CASE
+                    WHEN p.status = 'ACTIVE' AND p.deleted_at IS NULL
+                         AND p.merged_at IS NULL AND p.anonymized_at IS NULL THEN 'RESOLVED'
+                    ELSE 'CLOSED'
+                END,
+                closed_at = NOW()
+            FROM patients p
+            WHERE p.id = e.patient_id
+              AND e.rule_id = $1
+              AND e.status = 'OPEN'
+              AND NOT (e.patient_id = ANY($2))
+              AND NOT (e.patient_id = ANY($3))
+            RETURNING e.status
+            "#,
+        )
+        .bind(rule.id)
+        .bind(&overdue_ids)
+        .bind(&selection.unreadable)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let opted_out: HashSet<Uuid> = sqlx::query_scalar(
+            r#"
+            SELECT patient_id FROM recall_opt_outs
+            WHERE patient_id = ANY($1) AND (rule_id IS NULL OR rule_id = $2)
+            "#,
+        )
+        .bind(&overdue_ids)
+        .bind(rule.id)
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .collect();
+
+        tx.commit().await?;
+
+        let mut counts = RunCounts {
+            overdue: overdue.len(),
+            resolved: finished
+                .iter()
+                .filter(|status| status.as_str() == RecallEntryStatus::Resolved.as_str())
+                .count(),
+            unreadable: selection.unreadable.len(),
+            ..Default::default()
+        };
+
+        let remind_before = Utc::now() - Duration::days(rule.reminder_interval_days as i64);
+        for patient in overdue {
+            let patient_id = patient.member.patient_id;
+            if opted_out.contains(&patient_id) {
+                counts.opted_out += 1;
+                continue;
+            }
+
+            let (entry, created) = self.open_entry(rule.id, patient_id).await?;
+            if created {
+                counts.new_entries += 1;
+            }
+
+            let reminder_due = entry.reminders_sent < rule.max_reminders
+                && entry
+                    .last_reminded_at
+                    .is_none_or(|reminded_at| reminded_at <= remind_before);
+            if !reminder_due {
+                continue;
+            }
+
+            match self
+                .remind(rule, &patient, entry.id, notification_service)
+                .await
+            {
+                Ok(notification_id) => {
+                    sqlx::query(
+                        r#"
+                        UPDATE recall_entries
+                        SET reminders_sent = reminders_sent + 1,
+                            last_reminded_at = NOW(),
+                            last_notification_id = $2,
+                            last_skip_reason = NULL
+                        WHERE id = $1
+                        "#,
+                    )
+                    .bind(entry.id)
+                    .bind(notification_id)
+                    .execute(&self.pool)
+                    .await?;
+                    counts.reminded += 1;
+                }
+                Err(reason) => {
+                    sqlx::query("UPDATE recall_entries SET last_skip_reason = $2 WHERE id = $1")
+                        .bind(entry.id)
+                        .bind(reason)
+                        .execute(&self.pool)
+                        .await?;
+                    counts.skipped += 1;
+                }
+            }
+        }
+
+        Ok(counts)
+    }
+
+    /// Open entry of a patient on a rule's list, added if missing; returns
+    /// whether it was added
+    async fn open_entry(&self, rule_id: Uuid, patient_id: Uuid) -> Result<(RecallEntry, bool)> {
+        let inserted = sqlx::query_as::<_, RecallEntry>(&format!(
+            r#"
+            INSERT INTO recall_entries AS e (rule_id, patient_id)
+            VALUES ($1, $2)
+            ON CONFLICT (rule_id, patient_id) WHERE status = 'OPEN' DO NOTHING
+            RETURNING {}
+            "#,
+            ENTRY_COLUMNS
+        ))
+        .bind(rule_id)
+        .bind(patient_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        if let Some(entry) = inserted {
+            return Ok((entry, true));
+        }
+
+        let entry = sqlx::query_as::<_, RecallEntry>(&format!(
+            r#"
+            SELECT {} FROM recall_entries e
+            WHERE e.rule_id = $1 AND e.patient_id = $2 AND e.status = 'OPEN'
+            "#,
+            ENTRY_COLUMNS
+        ))
+        .bind(rule_id)
+        .bind(patient_id)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok((entry, false))
+    }
+
+    /// Queue a reminder, returning the notification id or why it was not
+    /// queued
+    async fn remind(
+        &self,
+        rule: &RecallRuleResponse,
+        patient: &SelectedPatient,
+        entry_id: Uuid,
+        notification_service: &NotificationService,
+    ) -> std::result::Result<Uuid, String> {
+        let patient_id = patient.member.patient_id;
+        let email = patient
+            .email
+            .as_deref()
+            .and_then(|email| self.encryption_key.decrypt(email).ok())
+            .filter(|email| !email.trim().is_empty())
+            .ok_or_else(|| "No email address".to_string())?;
+        if !notification_service
+            .can_patient_receive_email(patient_id, rule.created_by)
+            .await
+        {
+            return Err("Email notifications disabled".to_string());
+        }
+
+        let patient_name = format!("{} {}", patient.member.first_name, patient.member.last_name);
+        notification_service
+            .queue_recall_reminder(
+                patient_id,
+                &email,
+                &patient_name,
+                &rule.subject,
+                &rule.message_body,
+                rule.id,
+                entry_id,
+                rule.created_by,
+            )
+            .await
+            .map(|notification| notification.id)
+            .map_err(|e| {
+                warn!(
+                    "Failed to queue recall reminder for patient {}: {}",
+                    patient_id, e
+                );
+                "Notification could not be queued".to_string()
+            })
+    }
+}
+
+fn to_response(row: RecallRule) -> Result<RecallRuleResponse> {
+    RecallRuleResponse::try_from(row).map_err(AppError::Internal)
+}
+
+/// Spawn the scheduled runs of recall rules as a background task
+///
+/// Checks every five minutes; a rule is due from the start of its next
+/// period (see `ReportCadence::next_run_after`).
+pub fn spawn_recall_job(pool: PgPool, email_service: EmailService, encryption_key: EncryptionKey) {
+    let notification_service = NotificationService::new(pool.clone(), email_service);
+    let service = RecallService::new(pool, encryption_key);
+
+    tokio::spawn(async move {
+        loop {
+            match service.run_due(&notification_service).await {
+                Ok(0) => {}
+                Ok(count) => info!("Ran {} recall rules", count),
+                Err(e) => error!("Recall job failed: {}", e),
+            }
+
+            sleep(TokioDuration::from_secs(CHECK_INTERVAL_SECS)).await;
+        }
+    });
+
+    info!("Recall job spawned as background task");
+}
@@ -0,0 +1,484 @@
+/*!
+ * Reference Data Cache
+ *
+ * Read-through cache for data that is read on almost every request or
+ * keystroke and rarely written: system settings, medication catalog search
+ * and document templates. Writers invalidate the affected namespace, so a
+ * change is visible on the next read; entries also expire after the TTL of
+ * their namespace in case a write bypassed the cache (e.g. the
+ * `import_medications` binary).
+ *
+ * Entries are stored as JSON, in memory (default, per instance) or in Redis
+ * when REDIS_ENABLED=true (shared by all instances, so an invalidation on
+ * one replica applies to all; needs the `redis-cache` feature). A cache
+ * failure is logged and the data is read from the database.
+ *
+ * ICD-10 search is not cached: its codes are a static list in
+ * `VisitDiagnosisService` and never hit the database.
+ */
+
+use anyhow::{Context, Result};
+use futures::future::BoxFuture;
+use serde::{de::DeserializeOwned, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// Number of entries above which expired entries are dropped
+const MAX_MEMORY_ENTRIES: usize = 10_000;
+
+/// Kind of data cached, with its own key prefix and TTL
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CacheNamespace {
+    Settings,
+    Medications,
+    DocumentTemplates,
+}
+
+impl CacheNamespace {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CacheNamespace::Settings => "settings",
+            CacheNamespace::Medications => "medications",
+            CacheNamespace::DocumentTemplates => "document_templates",
+        }
+    }
+
+    /// How long entries are kept without an invalidation
+    pub fn ttl(&self) -> Duration {
+        match self {
+            CacheNamespace::Settings => Duration::from_secs(300),
+            CacheNamespace::Medications => Duration::from_secs(600),
+            CacheNamespace::DocumentTemplates => Duration::from_secs(600),
+        }
+    }
+
+    fn key(&self, key: &str) -> String {
+        format!("{}:{}", self.as_str(), key)
+    }
+}
+
+/// Where cached entries are stored
+pub trait CacheStore: Send + Sync {
+    /// Store name, for logs
+    fn name(&self) -> &'static str;
+
+    fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<Option<String>>>;
+
+    fn set<'a>(&'a self, key: &'a str, value: String, ttl: Duration) -> BoxFuture<'a, Result<()>>;
+
+    fn remove<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<()>>;
+
+    /// Remove every entry whose key starts with `prefix`
+    fn remove_prefix<'a>(&'a self, prefix: &'a str) -> BoxFuture<'a, Result<()>>;
+}
+
+/// Entries of this instance only
+#[derive(Default)]
+pub struct MemoryCacheStore {
+    entries: RwLock<HashMap<String, (String, Instant)>>,
+}
+
+impl MemoryCacheStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn get_sync(&self, key: &str) -> Option<String> {
+        let entries = self.entries.read().ok()?;
+        entries
+            .get(key)
+            .filter(|(_, expires_at)| *expires_at > Instant::now())
+            .map(|(value, _)| value.clone())
+    }
+
+    fn set_sync(&self, key: &str, value: String, ttl: Duration) {
+        if let Ok(mut entries) = self.entries.write() {
+            if entries.len() >= MAX_MEMORY_ENTRIES {
+                let now = Instant::now();
+                entries.retain(|_, (_, expires_at)| *expires_at > now);
+                if entries.len() >= MAX_MEMORY_ENTRIES {
+                    entries.clear();
+                }
+            }
+            entries.insert(key.to_string(), (value, Instant::now() + ttl));
+        }
+    }
+
+    fn remove_where(&self, matches: impl Fn(&str) -> bool) {
+        if let Ok(mut entries) = self.entries.write() {
+            entries.retain(|key, _| !matches(key));
+        }
+    }
+}
+
+impl CacheStore for MemoryCacheStore {
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+
+    fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<Option<String>>> {
+        let value = self.get_sync(key);
+        Box::pin(async move { Ok(value) })
+    }
+
+    fn set<'a>(&'a self, key: &'a str, value: String, ttl: Duration) -> BoxFuture<'a, Result<()>> {
+        self.set_sync(key, value, ttl);
+        Box::pin(async { Ok(()) })
+    }
+
+    fn remove<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<()>> {
+        self.remove_where(|k| k == key);
+        Box::pin(async { Ok(()) })
+    }
+
+    fn remove_prefix<'a>(&'a self, prefix: &'a str) -> BoxFuture<'a, Result<()>> {
+        self.remove_where(|k| k.starts_with(prefix));
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// Entries in Redis, shared by all instances
+#[cfg(feature = "redis-cache")]
+pub struct RedisCacheStore {
+    connection: redis::aio::ConnectionManager,
+}
+
+#[cfg(feature = "redis-cache")]
+impl RedisCacheStore {
+    /// Connect to Redis
+    pub async fn connect(url: &str) -> Result<Self> {
+        let client = redis::Client::open(url).context("Invalid REDIS_URL")?;
+        let connection = redis::aio::ConnectionManager::new(client)
+            .await
+            .context("Failed to connect to Redis")?;
+
+        Ok(Self { connection })
+    }
+
+    fn redis_key(key: &str) -> String {
+        format!("docpat:cache:{}", key)
+    }
+}
+
+#[cfg(feature = "redis-cache")]
+impl CacheStore for RedisCacheStore {
+    fn name(&self) -> &'static str {
+        "redis"
+    }
+
+    fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<Option<String>>> {
+        Box::pin(async move {
+            let mut connection = self.connection.clone();
+            let value: Option<String> = redis::cmd("GET")
+                .arg(Self::redis_key(key))
+                .query_async(&mut connection)
+                .await
+                .context("Failed to read cache entry from Redis")?;
+            Ok(value)
+        })
+    }
+
+    fn set<'a>(&'a self, key: &'a str, value: String, ttl: Duration) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let mut connection = self.connection.clone();
+            let _: () = redis::cmd("SET")
+                .arg(Self::redis_key(key))
+                .arg(value)
+                .arg("EX")
+                .arg(ttl.as_secs().max(1))
+                .query_async(&mut connection)
+                .await
+                .context("Failed to write cache entry to Redis")?;
+            Ok(())
+        })
+    }
+
+    fn remove<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let mut connection = self.connection.clone();
+            let _: () = redis::cmd("DEL")
+                .arg(Self::redis_key(key))
+                .query_async(&mut connection)
+                .await
+                .context("Failed to remove cache entry from Redis")?;
+            Ok(())
+        })
+    }
+
+    fn remove_prefix<'a>(&'a self, prefix: &'a str) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let mut connection = self.connection.clone();
+            let pattern = format!("{}*", Self::redis_key(prefix));
+            let mut cursor: u64 = 0;
+            loop {
+                let (next, keys): (u64, Vec<String>) = redis::cmd("SCAN")
+                    .arg(cursor)
+                    .arg("MATCH")
+                    .arg(&pattern)
+                    .arg("COUNT")
+                    .arg(500)
+                    .query_async(&mut connection)
+                    .await
+                    .context("Failed to scan cache entries in Redis")?;
+                if !keys.is_empty() {
+                    let _: () = redis::cmd("DEL")
+                        .arg(keys)
+                        .query_async(&mut connection)
+                        .await
+                        .context("Failed to remove cache entries from Redis")?;
+                }
+                if next == 0 {
+                    return Ok(());
+                }
+                cursor = next;
+            }
+        })
+    }
+}
+
+/// Cache of reference data shared by all requests
+#[derive(Clone)]
+pub struct ReferenceCache {
+    store: Arc<dyn CacheStore>,
+}
+
+impl ReferenceCache {
+    /// Cache backed by the given store
+    pub fn new(store: Arc<dyn CacheStore>) -> Self {
+        Self { store }
+    }
+
+    /// In-memory cache of this instance
+    pub fn memory() -> Self {
+        Self::new(Arc::new(MemoryCacheStore::new()))
+    }
+
+    /// Redis cache if REDIS_ENABLED=true (using REDIS_URL), in-memory otherwise
+    pub async fn from_env() -> Result<Self> {
+        let enabled = std::env::var("REDIS_ENABLED")
+            .map(|v| v.trim().eq_ignore_ascii_case("true"))
+            .unwrap_or(false);
+        if !enabled {
+            return Ok(Self::memory());
+        }
+
+        let url = std::env::var("REDIS_URL")
+            .ok()
+            .filter(|url| !url.trim().is_empty())
+            .context("REDIS_ENABLED=true requires REDIS_URL")?;
+        Self::redis(&url).await
+    }
+
+    #[cfg(feature = "redis-cache")]
+    async fn redis(url: &str) -> Result<Self> {
+        Ok(Self::new(Arc::new(RedisCacheStore::connect(url).await?)))
+    }
+
+    #[cfg(not(feature = "redis-cache"))]
+    async fn redis(_url: &str) -> Result<Self> {
+        anyhow::bail!("REDIS_ENABLED=true requires the redis-cache feature")
+    }
+
+    /// Name of the store
+    pub fn store_name(&self) -> &'static str {
+        self.store.name()
+    }
+
+    /// Cached value of a key, if present and readable
+    pub async fn get<T: DeserializeOwned>(
+        &self,
+        namespace: CacheNamespace,
+        key: &str,
+    ) -> Option<T> {
+        let key = namespace.key(key);
+        match self.store.get(&key).await {
+            Ok(Some(json)) => match serde_json::from_str(&json) {
+                Ok(value) => Some(value),
+                Err(e) => {
+                    warn!("Discarding unreadable cache entry {}: {}", key, e);
+                    let _ = self.store.remove(&key).await;
+                    None
+                }
+            },
+            Ok(None) => None,
+            Err(e) => {
+                warn!("Cache ({}) read failed: {:#}", self.store.name(), e);
+                None
+            }
+        }
+    }
+
+    /// Cache a value for the TTL of its namespace
+    pub async fn set<T: Serialize>(&self, namespace: CacheNamespace, key: &str, value: &T) {
+        self.set_with_ttl(namespace, key, value, namespace.ttl())
+            .await;
+    }
+
+    /// Cache a value for the given TTL
+    pub async fn set_with_ttl<T: Serialize>(
+        &self,
+        namespace: CacheNamespace,
+        key: &str,
+        value: &T,
+        ttl: Duration,
+    ) {
+        let json = match serde_json::to_string(value) {
+            Ok(json) => json,
+            Err(e) => {
+                warn!("Failed to serialize cache entry: {}", e);
+                return;
+            }
+        };
+        if let Err(e) = self.store.set(&namespace.key(key), json, ttl).await {
+            warn!("Cache ({}) write failed: {:#}", self.store.name(), e);
+        }
+    }
+
+    /// Cached value of a key, or the loaded one (then cached)
+    pub async fn get_or_load<T, E, F, Fut>(
+        &self,
+        namespace: CacheNamespace,
+        key: &str,
+        load: F,
+    ) -> std::result::Result<T, E>
+    where
+        T: Serialize + DeserializeOwned,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = std::result::Result<T, E>>,
+    {
+        if let Some(value) = self.get(namespace, key).await {
+            return Ok(value);
+        }
+
+        let value = load().await?;
+        self.set(namespace, key, &value).await;
+        Ok(value)
+    }
+
+    /// Drop one entry after a write
+    pub async fn invalidate(&self, namespace: CacheNamespace, key: &str) {
+        if let Err(e) = self.store.remove(&namespace.key(key)).await {
+            warn!("Cache ({}) invalidation failed: {:#}", self.store.name(), e);
+        }
+    }
+
+    /// Drop every entry of a namespace after a write
+    pub async fn invalidate_all(&self, namespace: CacheNamespace) {
+        if let Err(e) = self.store.remove_prefix(&namespace.key("")).await {
+            warn!("Cache ({}) invalidation failed: {:#}", self.store.name(), e);
+        }
+    }
+}
+
+impl Default for ReferenceCache {
+    fn default() -> Self {
+        Self::memory()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_get_or_load_caches_value() {
+        let cache = ReferenceCache::memory();
+        let mut loads = 0;
+
+        for _ in 0..3 {
+            let value: std::result::Result<Vec<String>, ()> = cache
+                .get_or_load(CacheNamespace::Medications, "search:tach:20", || {
+                    loads += 1;
+                    async { Ok(vec!["Tachipirina".to_string()]) }
+                })
+                .await;
+            assert_eq!(value.unwrap(), vec!["Tachipirina".to_string()]);
+        }
+
+        assert_eq!(loads, 1);
+    }
+
+    #[tokio::test]
+    async fn test_load_errors_are_not_cached() {
+        let cache = ReferenceCache::memory();
+
+        let value: std::result::Result<i32, &str> = cache
+            .get_or_load(CacheNamespace::Settings, "clinic.name", || async {
+                Err("down")
+            })
+            .await;
+        assert!(value.is_err());
+        assert_eq!(
+            cache
+                .get::<i32>(CacheNamespace::Settings, "clinic.name")
+                .await,
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_invalidate_namespace() {
+        let cache = ReferenceCache::memory();
+        cache
+            .set(CacheNamespace::Medications, "search:a:20", &1)
+            .await;
+        cache
+            .set(CacheNamespace::Medications, "search:b:20", &2)
+            .await;
+        cache.set(CacheNamespace::Settings, "search:a:20", &3).await;
+
+        cache.invalidate_all(CacheNamespace::Medications).await;
+
+        assert_eq!(
+            cache
+                .get::<i32>(CacheNamespace::Medications, "search:a:20")
+                .await,
+            None
+        );
+        assert_eq!(
+            cache
+                .get::<i32>(CacheNamespace::Medications, "search:b:20")
+                .await,
+            None
+        );
+        assert_eq!(
+            cache
+                .get::<i32>(CacheNamespace::Settings, "search:a:20")
+                .await,
+            Some(3)
+        );
+
+        cache
+            .invalidate(CacheNamespace::Settings, "search:a:20")
+            .await;
+        assert_eq!(
+            cache
+                .get::<i32>(CacheNamespace::Settings, "search:a:20")
+                .await,
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_entries_expire() {
+        let cache = ReferenceCache::memory();
+        cache
+            .set_with_ttl(
+                CacheNamespace::Settings,
+                "clinic.name",
+                &"Studio",
+                Duration::ZERO,
+            )
+            .await;
+
+        assert_eq!(
+            cache
+                .get::<String>(CacheNamespace::Settings, "clinic.name")
+                .await,
+            None
+        );
+    }
+}
@@ -0,0 +1,585 @@
+/*!
+ * Referral Service
+ *
+ * Outgoing specialist referrals under RLS (doctors refer and edit, nurses
+ * read). The reason, clinical information and response summary are
+ * encrypted.
+ *
+ * Referrals are medical records: deleting one only marks it deleted.
+ *
+ * Status moves forward only (PENDING -> SENT -> RESPONDED, or CANCELLED
+ * before a response). Overdue referrals are listed practice-wide by
+ * `list_overdue`; the reminder emails are queued by the notification
+ * scheduler. The referral letter itself is rendered by
+ * `DocumentService::generate_referral_letter`.
+ */
+
+use std::collections::HashMap;
+
+use crate::{
+    db::rls::begin_with_rls,
+    models::{
+        CreateReferralRequest, ListReferralsQuery, OverdueReferral, OverdueReferralsQuery,
+        Referral, ReferralResponse, ReferralStatus, UpdateReferralRequest,
+    },
+    utils::{encryption::EncryptionKey, AppError, Result},
+};
+use chrono::{NaiveDate, Utc};
+use sqlx::{PgConnection, PgPool};
+use tracing::info;
+use uuid::Uuid;
+
+const REFERRAL_COLUMNS: &str = r#"
+    id, patient_id, visit_id, visit_date, specialty, specialist_name, specialist_contact,
+    reason, clinical_info, urgency, status, sent_at, response_due_date, response_received_at,
+    response_summary, response_attachment_id, referral_document_id, last_reminder_at,
+    reminder_count, created_by, created_at, updated_by, updated_at
+"#;
+
+/// Maximum referrals in the overdue list
+const OVERDUE_LIMIT: i64 = 500;
+
+/// Referral service
+pub struct ReferralService {
+    pool: PgPool,
+    encryption_key: EncryptionKey,
+}
+
+impl ReferralService {
+    /// Create a new referral service
+    pub fn new(pool: PgPool, encryption_key: EncryptionKey) -> Self {
+        Self {
+            pool,
+            encryption_key,
+        }
+    }
+
+    /// Refer a patient to a specialist
+    ///
+    /// Fails with Validation for a due date in the past or a visit that is
+    /// not the patient's, and with Conflict if the patient is anonymized or
+    /// merged.
+    pub async fn create_referral(
+        &self,
+        patient_id: Uuid,
+        req: &CreateReferralRequest,
+        user_id: Uuid,
+    ) -> Result<ReferralResponse> {
+        let today = Utc::now().date_naive();
+        let response_due_date = req
+            .response_due_date
+            .unwrap_or_else(|| req.urgency.default_due_date(today));
+        if response_due_date < today {
+            return Err(AppError::Validation(
+                "response_due_date cannot be in the past".to_string(),
+            ));
+        }
+
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        self.check_patient(&mut tx, patient_id).await?;
+
+        let visit_date = match req.visit_id {
+            Some(visit_id) => Some(self.visit_date(&mut tx, patient_id, visit_id).await?),
+            None => None,
+        };
+
+        let (status, sent_at) = if req.sent {
+            (ReferralStatus::Sent, Some(Utc::now()))
+        } else {
+            (ReferralStatus::Pending, None)
+        };
+
+        let referral = sqlx::query_as::<_, Referral>(&format!(
+            r#"
+            INSERT INTO referrals (
+                patient_id, visit_id, visit_date, specialty, specialist_name, specialist_contact,
+                reason, clinical_info, urgency, status, sent_at, response_due_date, created_by
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+            RETURNING {}
+            "#,
+            REFERRAL_COLUMNS
+        ))
+        .bind(patient_id)
+        .bind(req.visit_id)
+        .bind(visit_date)
+        .bind(req.specialty.trim())
+        .bind(req.specialist_name.as_deref().map(str::trim))
+        .bind(req.specialist_contact.as_deref().map(str::trim))
+        .bind(self.encrypt(req.reason.trim())?)
+        .bind(self.encrypt_optional(&req.clinical_info)?)
+        .bind(req.urgency.as_str())
+        .bind(status.as_str())
+        .bind(sent_at)
+        .bind(response_due_date)
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        info!(
+            "Referral {} ({}) created for patient {} by {}",
+            referral.id, referral.specialty, patient_id, user_id
+        );
+
+        self.decrypt_referral(&referral)
+    }
+
+    /// List a patient's referrals, newest first
+    pub async fn list_referrals(
+        &self,
+        patient_id: Uuid,
+        query: &ListReferralsQuery,
+        user_id: Uuid,
+    ) -> Result<Vec<ReferralResponse>> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        let referrals = sqlx::query_as::<_, Referral>(&format!(
+            r#"
+            SELECT {}
+            FROM referrals
+            WHERE deleted_at IS NULL
+              AND patient_id = $1
+              AND ($2::TEXT IS NULL OR status = $2)
+              AND ($3::UUID IS NULL OR visit_id = $3)
+              AND (NOT $4 OR (status = 'SENT' AND response_due_date < CURRENT_DATE))
+            ORDER BY created_at DESC
+            "#,
+            REFERRAL_COLUMNS
+        ))
+        .bind(patient_id)
+        .bind(query.status.map(|s| s.as_str()))
+        .bind(query.visit_id)
+        .bind(query.overdue_only)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        referrals.iter().map(|r| self.decrypt_referral(r)).collect()
+    }
+
+    /// Get a referral
+    pub async fn get_referral(
+        &self,
+        patient_id: Uuid,
+        referral_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<ReferralResponse> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        let referral = self.find_referral(&mut tx, patient_id, referral_id).await?;
+        tx.commit().await?;
+
+        self.decrypt_referral(&referral)
+    }
+
+    /// Update a referral, recording its sending or the specialist response
+    ///
+    /// Fails with Conflict for a status change the referral cannot make (see
+    /// `ReferralStatus::can_transition_to`) and for any change to a
+    /// cancelled referral; with Validation for response details on a
+    /// referral without a response, or an attachment that is not the
+    /// patient's.
+    pub async fn update_referral(
+        &self,
+        patient_id: Uuid,
+        referral_id: Uuid,
+        req: &UpdateReferralRequest,
+        user_id: Uuid,
+    ) -> Result<ReferralResponse> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        let existing = self.find_referral(&mut tx, patient_id, referral_id).await?;
+
+        let current = ReferralStatus::from_db(&existing.status).ok_or_else(|| {
+            AppError::Internal(format!("Unknown referral status {}", existing.status))
+        })?;
+        if current == ReferralStatus::Cancelled {
+            return Err(AppError::Conflict(format!(
+                "Referral {} has been cancelled",
+                referral_id
+            )));
+        }
+
+        let status = match req.status {
+            Some(next) if next != current => {
+                if !current.can_transition_to(next) {
+                    return Err(AppError::Conflict(format!(
+                        "Referral {} cannot move from {} to {}",
+                        referral_id,
+                        current.as_str(),
+                        next.as_str()
+                    )));
+                }
+                next
+            }
+            _ => current,
+        };
+
+        let has_response_details = req.response_summary.is_some()
+            || req.response_attachment_id.is_some()
+            || req.response_received_at.is_some();
+        if has_response_details && status != ReferralStatus::Responded {
+            return Err(AppError::Validation(
+                "Response details can only be recorded on a RESPONDED referral".to_string(),
+            ));
+        }
+
+        if let Some(attachment_id) = req.response_attachment_id {
+            self.check_attachment(&mut tx, patient_id, attachment_id)
+                .await?;
+        }
+
+        // A response that arrives before the referral was marked sent: it
+        // was sent by then
+        let now = Utc::now();
+        let sent_at = match status {
+            ReferralStatus::Sent | ReferralStatus::Responded => {
+                req.sent_at.or(existing.sent_at).or(Some(now))
+            }
+            _ => existing.sent_at,
+        };
+        let response_received_at = match status {
+            ReferralStatus::Responded => req
+                .response_received_at
+                .or(existing.response_received_at)
+                .or(Some(now)),
+            _ => existing.response_received_at,
+        };
+        if let (Some(sent), Some(received)) = (sent_at, response_received_at) {
+            if received < sent {
+                return Err(AppError::Validation(
+                    "response_received_at cannot be before sent_at".to_string(),
+                ));
+            }
+        }
+
+        sqlx::query(
+            r#"
+            UPDATE referrals
+            SET status = $3,
+                specialty = COALESCE($4, specialty),
+                specialist_name = COALESCE($5, specialist_name),
+                specialist_contact = COALESCE($6, specialist_contact),
+                reason = COALESCE($7, reason),
+                clinical_info = COALESCE($8, clinical_info),
+                urgency = COALESCE($9, urgency),
+                response_due_date = COALESCE($10, response_due_date),
+                sent_at = $11,
+                response_received_at = $12,
+                response_summary = COALESCE($13, response_summary),
+                response_attachment_id = COALESCE($14, response_attachment_id),
+                updated_by = $15
+            WHERE id = $1 AND patient_id = $2 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(referral_id)
+        .bind(patient_id)
+        .bind(status.as_str())
+        .bind(req.specialty.as_deref().map(str::trim))
+        .bind(req.specialist_name.as_deref().map(str::trim))
+        .bind(req.specialist_contact.as_deref().map(str::trim))
+        .bind(
+            req.reason
+                .as_deref()
+                .map(|r| self.encrypt(r.trim()))
+                .transpose()?,
+        )
+        .bind(self.encrypt_optional(&req.clinical_info)?)
+        .bind(req.urgency.map(|u| u.as_str()))
+        .bind(req.response_due_date)
+        .bind(sent_at)
+        .bind(response_received_at)
+        .bind(self.encrypt_optional(&req.response_summary)?)
+        .bind(req.response_attachment_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+        let referral = self.find_referral(&mut tx, patient_id, referral_id).await?;
+        tx.commit().await?;
+
+        if status != current {
+            info!(
+                "Referral {} moved from {} to {} by {}",
+                referral_id,
+                current.as_str(),
+                status.as_str(),
+                user_id
+            );
+        }
+
+        self.decrypt_referral(&referral)
+    }
+
+    /// Mark a referral deleted
+    pub async fn delete_referral(
+        &self,
+        patient_id: Uuid,
+        referral_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<()> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        let deleted = sqlx::query(
+            r#"
+            UPDATE referrals
+            SET deleted_at = NOW(), deleted_by = $3, updated_by = $3
+            WHERE id = $1 AND patient_id = $2 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(referral_id)
+        .bind(patient_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        if deleted == 0 {
+            return Err(AppError::NotFound(format!(
+                "Referral {} not found",
+                referral_id
+            )));
+        }
+
+        tx.commit().await?;
+
+        info!(
+            "Referral {} of patient {} deleted by {}",
+            referral_id, patient_id, user_id
+        );
+
+        Ok(())
+    }
+
+    /// Referrals of every patient still waiting for a response after their
+    /// due date, most overdue first
+    pub async fn list_overdue(
+        &self,
+        query: &OverdueReferralsQuery,
+        user_id: Uuid,
+    ) -> Result<Vec<OverdueReferral>> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        let referrals = sqlx::query_as::<_, Referral>(&format!(
+            r#"
+            SELECT {}
+            FROM referrals
+            WHERE deleted_at IS NULL
+              AND status = 'SENT'
+              AND response_due_date < CURRENT_DATE
+              AND ($1::UUID IS NULL OR created_by = $1)
+            ORDER BY response_due_date, created_at
+            LIMIT $2
+            "#,
+            REFERRAL_COLUMNS
+        ))
+        .bind(query.referred_by)
+        .bind(OVERDUE_LIMIT)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let patient_ids: Vec<Uuid> = referrals.iter().map(|r| r.patient_id).collect();
+        let names: Vec<(Uuid, String, String)> =
+            sqlx::query_as("SELECT id, first_name, last_name FROM patients WHERE id = ANY($1)")
+                .bind(&patient_ids)
+                .fetch_all(&mut *tx)
+                .await?;
+
+        tx.commit().await?;
+
+        let mut patient_names = HashMap::with_capacity(names.len());
+        for (id, first_name, last_name) in names {
+            let first_name = self.decrypt_name(&first_name)?;
+            let last_name = self.decrypt_name(&last_name)?;
+            patient_names.insert(id, format!("{} {}", first_name, last_name));
+        }
+
+        let today = Utc::now().date_naive();
+        referrals
+            .iter()
+            .map(|referral| {
+                Ok(OverdueReferral {
+                    patient_name: patient_names
+                        .get(&referral.patient_id)
+                        .cloned()
+                        .unwrap_or_default(),
+                    days_overdue: (today - referral.response_due_date).num_days(),
+                    referral: self.decrypt_referral(referral)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Get a referral ready to be printed as a letter
+    ///
+    /// Fails with Conflict if the referral has been cancelled.
+    pub async fn prepare_letter(
+        &self,
+        patient_id: Uuid,
+        referral_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<ReferralResponse> {
+        let referral = self.get_referral(patient_id, referral_id, user_id).await?;
+
+        if referral.status == ReferralStatus::Cancelled.as_str() {
+            return Err(AppError::Conflict(format!(
+                "Referral {} has been cancelled",
+                referral_id
+            )));
+        }
+
+        Ok(referral)
+    }
+
+    /// Link the letter generated for a referral
+    pub async fn link_letter(
+        &self,
+        patient_id: Uuid,
+        referral_id: Uuid,
+        document_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<ReferralResponse> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        sqlx::query(
+            r#"
+            UPDATE referrals
+            SET referral_document_id = $3, updated_by = $4
+            WHERE id = $1 AND patient_id = $2 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(referral_id)
+        .bind(patient_id)
+        .bind(document_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+        let referral = self.find_referral(&mut tx, patient_id, referral_id).await?;
+        tx.commit().await?;
+
+        info!(
+            "Referral letter {} generated for referral {} by {}",
+            document_id, referral_id, user_id
+        );
+
+        self.decrypt_referral(&referral)
+    }
+
+    async fn find_referral(
+        &self,
+        tx: &mut PgConnection,
+        patient_id: Uuid,
+        referral_id: Uuid,
+    ) -> Result<Referral> {
+        sqlx::query_as::<_, Referral>(&format!(
+            r#"
+            SELECT {}
+            FROM referrals
+            WHERE id = $1 AND patient_id = $2 AND deleted_at IS NULL
+            "#,
+            REFERRAL_COLUMNS
+        ))
+        .bind(referral_id)
+        .bind(patient_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Referral {} not found", referral_id)))
+    }
+
+    /// The attachment must be one of the patient's
+    async fn check_attachment(
+        &self,
+        tx: &mut PgConnection,
+        patient_id: Uuid,
+        attachment_id: Uuid,
+    ) -> Result<()> {
+        let exists: bool = sqlx::query_scalar(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM patient_attachments
+                WHERE id = $1 AND patient_id = $2 AND deleted_at IS NULL
+            )
+            "#,
+        )
+        .bind(attachment_id)
+        .bind(patient_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if !exists {
+            return Err(AppError::Validation(format!(
+                "Attachment {} is not an attachment of this patient",
+                attachment_id
+            )));
+        }
+        Ok(())
+    }
+
+    /// The patient must exist and be neither anonymized nor merged
+    async fn check_patient(&self, tx: &mut PgConnection, patient_id: Uuid) -> Result<()> {
+        let patient: Option<(
+            Option<chrono::DateTime<chrono::Utc>>,
+            Option<chrono::DateTime<chrono::Utc>>,
+        )> = sqlx::query_as("SELECT anonymized_at, merged_at FROM patients WHERE id = $1")
+            .bind(patient_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        match patient {
+            None => Err(AppError::NotFound(format!(
+                "Patient {} not found",
+                patient_id
+            ))),
+            Some((Some(_), _)) => Err(AppError::Conflict(format!(
+                "Patient {} has been anonymized",
+                patient_id
+            ))),
+            Some((_, Some(_))) => Err(AppError::Conflict(format!(
+                "Patient {} has been merged into another record",
+                patient_id
+            ))),
+            Some(_) => Ok(()),
+        }
+    }
+
+    /// Date of a visit of the patient (the visits partition key)
+    async fn visit_date(
+        &self,
+        tx: &mut PgConnection,
+        patient_id: Uuid,
+        visit_id: Uuid,
+    ) -> Result<NaiveDate> {
+        sqlx::query_scalar("SELECT visit_date FROM visits WHERE id = $1 AND patient_id = $2")
+            .bind(visit_id)
+            .bind(patient_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| {
+                AppError::Validation(format!("Visit {} is not a visit of this patient", visit_id))
+            })
+    }
+
+    fn decrypt_referral(&self, referral: &Referral) -> Result<ReferralResponse> {
+        referral
+            .decrypt(&self.encryption_key)
+            .map_err(|e| AppError::Internal(format!("Failed to decrypt referral: {}", e)))
+    }
+
+    fn decrypt_name(&self, value: &str) -> Result<String> {
+        self.encryption_key
+            .decrypt(value)
+            .map_err(|e| AppError::Internal(format!("Failed to decrypt patient name: {}", e)))
+    }
+
+    fn encrypt(&self, value: &str) -> Result<String> {
+        self.encryption_key
+            .encrypt(value)
+            .map_err(|e| AppError::Internal(format!("Failed to encrypt referral data: {}", e)))
+    }
+
+    fn encrypt_optional(&self, value: &Option<String>) -> Result<Option<String>> {
+        value.as_deref().map(|v| self.encrypt(v)).transpose()
+    }
+}
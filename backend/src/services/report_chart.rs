@@ -0,0 +1,371 @@
+/*!
+ * Report Charts
+ *
+ * Bar, line and age pyramid charts drawn into the PDF report exports.
+ * genpdf only draws lines and text, so bars are thick strokes; the pie
+ * charts of the Excel exports are shown as bars with their share of the
+ * total.
+ */
+
+use genpdf::error::Error;
+use genpdf::render::Area;
+use genpdf::style::{Color, LineStyle, Style};
+use genpdf::{Context, Element, Mm, Position, RenderResult, Size};
+
+/// Width of a chart (fits an A4 page)
+const CHART_WIDTH: f64 = 160.0;
+
+/// Width reserved for the category labels of bar and pyramid charts
+const LABEL_WIDTH: f64 = 45.0;
+
+/// Width reserved right of the bars for their values
+const VALUE_WIDTH: f64 = 20.0;
+
+/// Height of a bar, a legend or a line of labels
+const ROW_HEIGHT: f64 = 5.0;
+
+/// Height of the plot of a line chart
+const PLOT_HEIGHT: f64 = 60.0;
+
+/// Width reserved left of a line chart plot for the value axis labels
+const AXIS_WIDTH: f64 = 15.0;
+
+/// Most categories shown on a bar or pyramid chart (keeps a chart on one page)
+const MAX_BARS: usize = 24;
+
+/// Most category labels printed under a line chart
+const MAX_AXIS_LABELS: usize = 6;
+
+const TITLE_FONT_SIZE: u8 = 10;
+const LABEL_FONT_SIZE: u8 = 8;
+
+/// Series colours, in order
+const PALETTE: [(u8, u8, u8); 4] = [
+    (49, 130, 189),
+    (49, 163, 84),
+    (230, 85, 13),
+    (117, 107, 177),
+];
+
+fn series_color(index: usize) -> Color {
+    let (r, g, b) = PALETTE[index % PALETTE.len()];
+    Color::Rgb(r, g, b)
+}
+
+fn pos(x: f64, y: f64) -> Position {
+    Position::new(x, y)
+}
+
+/// Largest value, at least 1 (scale of a chart without data)
+fn scale_max(values: impl Iterator<Item = f64>) -> f64 {
+    values.fold(1.0, f64::max)
+}
+
+/// Draw a filled horizontal bar from `x` to `x + length` on the row at `y`
+fn draw_bar(area: &Area<'_>, x: f64, y: f64, length: f64, color: Color) {
+    if length <= 0.0 {
+        return;
+    }
+    let middle = y + ROW_HEIGHT / 2.0;
+    area.draw_line(
+        vec![pos(x, middle), pos(x + length, middle)],
+        LineStyle::new()
+            .with_thickness(ROW_HEIGHT * 0.7)
+            .with_color(color),
+    );
+}
+
+/// Render result of a chart `height` high, or a page break if it does not fit
+fn fit(area: &Area<'_>, height: f64) -> Option<RenderResult> {
+    if area.size().height < Mm::from(height) {
+        Some(RenderResult {
+            size: Size::new(0, 0),
+            has_more: true,
+        })
+    } else {
+        None
+    }
+}
+
+fn rendered(height: f64) -> RenderResult {
+    RenderResult {
+        size: Size::new(CHART_WIDTH, height),
+        has_more: false,
+    }
+}
+
+/// Horizontal bar chart, one bar per category
+pub struct BarChart {
+    title: String,
+    bars: Vec<(String, f64)>,
+    shares: bool,
+}
+
+impl BarChart {
+    /// Create a bar chart of (category, value) pairs
+    pub fn new(title: impl Into<String>, bars: Vec<(String, f64)>) -> Self {
+        let mut bars = bars;
+        bars.truncate(MAX_BARS);
+        Self {
+            title: title.into(),
+            bars,
+            shares: false,
+        }
+    }
+
+    /// Label each bar with its share of the total (stands in for a pie chart)
+    pub fn with_shares(mut self) -> Self {
+        self.shares = true;
+        self
+    }
+}
+
+impl Element for BarChart {
+    fn render(
+        &mut self,
+        context: &Context,
+        area: Area<'_>,
+        style: Style,
+    ) -> Result<RenderResult, Error> {
+        let height = ROW_HEIGHT * (self.bars.len() as f64 + 2.0);
+        if let Some(result) = fit(&area, height) {
+            return Ok(result);
+        }
+
+        let font_cache = &context.font_cache;
+        let label_style = style.with_font_size(LABEL_FONT_SIZE);
+        area.print_str(
+            font_cache,
+            pos(0.0, 0.0),
+            style.bold().with_font_size(TITLE_FONT_SIZE),
+            &self.title,
+        )?;
+
+        let max = scale_max(self.bars.iter().map(|(_, value)| *value));
+        let total: f64 = self.bars.iter().map(|(_, value)| *value).sum();
+        let plot_width = CHART_WIDTH - LABEL_WIDTH - VALUE_WIDTH;
+
+        for (index, (label, value)) in self.bars.iter().enumerate() {
+            let y = ROW_HEIGHT * (index as f64 + 1.5);
+            let length = value / max * plot_width;
+            area.print_str(font_cache, pos(0.0, y), label_style, label)?;
+            draw_bar(&area, LABEL_WIDTH, y, length, series_color(0));
+
+            let value_label = if self.shares && total > 0.0 {
+                format!("{} ({:.1}%)", value, value / total * 100.0)
+            } else {
+                value.to_string()
+            };
+            area.print_str(
+                font_cache,
+                pos(LABEL_WIDTH + length + 1.5, y),
+                label_style,
+                value_label,
+            )?;
+        }
+
+        Ok(rendered(height))
+    }
+}
+
+/// Line chart of one or more series over the same categories
+pub struct LineChart {
+    title: String,
+    categories: Vec<String>,
+    series: Vec<(String, Vec<f64>)>,
+}
+
+impl LineChart {
+    /// Create a line chart over `categories` (the x axis, in order)
+    pub fn new(title: impl Into<String>, categories: Vec<String>) -> Self {
+        Self {
+            title: title.into(),
+            categories,
+            series: Vec::new(),
+        }
+    }
+
+    /// Add a series with one value per category
+    pub fn series(mut self, name: impl Into<String>, values: Vec<f64>) -> Self {
+        self.series.push((name.into(), values));
+        self
+    }
+}
+
+impl Element for LineChart {
+    fn render(
+        &mut self,
+        context: &Context,
+        area: Area<'_>,
+        style: Style,
+    ) -> Result<RenderResult, Error> {
+        let height = PLOT_HEIGHT + ROW_HEIGHT * 4.0;
+        if let Some(result) = fit(&area, height) {
+            return Ok(result);
+        }
+
+        let font_cache = &context.font_cache;
+        let label_style = style.with_font_size(LABEL_FONT_SIZE);
+        area.print_str(
+            font_cache,
+            pos(0.0, 0.0),
+            style.bold().with_font_size(TITLE_FONT_SIZE),
+            &self.title,
+        )?;
+
+        // Legend
+        for (index, (name, _)) in self.series.iter().enumerate() {
+            let x = AXIS_WIDTH + index as f64 * 35.0;
+            draw_bar(&area, x, ROW_HEIGHT, 6.0, series_color(index));
+            area.print_str(font_cache, pos(x + 8.0, ROW_HEIGHT), label_style, name)?;
+        }
+
+        // Axes, with the scale at the top of the value axis
+        let top = ROW_HEIGHT * 2.5;
+        let bottom = top + PLOT_HEIGHT;
+        let plot_width = CHART_WIDTH - AXIS_WIDTH;
+        let axis_style = LineStyle::new().with_thickness(0.2);
+        area.draw_line(
+            vec![
+                pos(AXIS_WIDTH, top),
+                pos(AXIS_WIDTH, bottom),
+                pos(CHART_WIDTH, bottom),
+            ],
+            axis_style,
+        );
+        let max = scale_max(
+            self.series
+                .iter()
+                .flat_map(|(_, values)| values.iter().copied()),
+        );
+        area.print_str(font_cache, pos(0.0, top), label_style, max.to_string())?;
+        area.print_str(font_cache, pos(0.0, bottom - ROW_HEIGHT), label_style, "0")?;
+
+        let count = self.categories.len();
+        let x_of = |index: usize| {
+            if count <= 1 {
+                AXIS_WIDTH + plot_width / 2.0
+            } else {
+                AXIS_WIDTH + index as f64 * plot_width / (count - 1) as f64
+            }
+        };
+
+        for (index, (_, values)) in self.series.iter().enumerate() {
+            let points: Vec<Position> = values
+                .iter()
+                .take(count)
+                .enumerate()
+                .map(|(i, value)| pos(x_of(i), bottom - value / max * PLOT_HEIGHT))
+                .collect();
+            if points.len() > 1 {
+                area.draw_line(
+                    points,
+                    LineStyle::new()
+                        .with_thickness(0.5)
+                        .with_color(series_color(index)),
+                );
+            }
+        }
+
+        // Only a few category labels fit under the plot
+        let step = count.div_ceil(MAX_AXIS_LABELS).max(1);
+        for (index, label) in self.categories.iter().enumerate().step_by(step) {
+            area.print_str(
+                font_cache,
+                pos(x_of(index) - 5.0, bottom + 1.0),
+                label_style,
+                label,
+            )?;
+        }
+
+        Ok(rendered(height))
+    }
+}
+
+/// Population pyramid: one row per age group, male bars to the left and
+/// female bars to the right of a shared axis
+pub struct PyramidChart {
+    title: String,
+    rows: Vec<(String, f64, f64)>,
+}
+
+impl PyramidChart {
+    /// Create a pyramid of (age group, male, female) rows, youngest first
+    pub fn new(title: impl Into<String>, rows: Vec<(String, f64, f64)>) -> Self {
+        let mut rows = rows;
+        rows.truncate(MAX_BARS);
+        Self {
+            title: title.into(),
+            rows,
+        }
+    }
+}
+
+impl Element for PyramidChart {
+    fn render(
+        &mut self,
+        context: &Context,
+        area: Area<'_>,
+        style: Style,
+    ) -> Result<RenderResult, Error> {
+        let height = ROW_HEIGHT * (self.rows.len() as f64 + 3.0);
+        if let Some(result) = fit(&area, height) {
+            return Ok(result);
+        }
+
+        let font_cache = &context.font_cache;
+        let label_style = style.with_font_size(LABEL_FONT_SIZE);
+        area.print_str(
+            font_cache,
+            pos(0.0, 0.0),
+            style.bold().with_font_size(TITLE_FONT_SIZE),
+            &self.title,
+        )?;
+
+        let half_width = (CHART_WIDTH - LABEL_WIDTH) / 2.0;
+        let axis = LABEL_WIDTH + half_width;
+        let max = scale_max(
+            self.rows
+                .iter()
+                .flat_map(|(_, male, female)| [*male, *female]),
+        );
+
+        // Legend
+        draw_bar(&area, LABEL_WIDTH, ROW_HEIGHT, 6.0, series_color(0));
+        area.print_str(
+            font_cache,
+            pos(LABEL_WIDTH + 8.0, ROW_HEIGHT),
+            label_style,
+            "Male",
+        )?;
+        draw_bar(&area, axis + 2.0, ROW_HEIGHT, 6.0, series_color(2));
+        area.print_str(
+            font_cache,
+            pos(axis + 10.0, ROW_HEIGHT),
+            label_style,
+            "Female",
+        )?;
+
+        // Oldest group at the top
+        for (index, (label, male, female)) in self.rows.iter().rev().enumerate() {
+            let y = ROW_HEIGHT * (index as f64 + 2.5);
+            area.print_str(
+                font_cache,
+                pos(0.0, y),
+                label_style,
+                format!("{} ({} / {})", label, male, female),
+            )?;
+            let male_length = male / max * half_width;
+            draw_bar(&area, axis - male_length, y, male_length, series_color(0));
+            draw_bar(&area, axis, y, female / max * half_width, series_color(2));
+        }
+
+        let bottom = ROW_HEIGHT * (self.rows.len() as f64 + 2.5);
+        area.draw_line(
+            vec![pos(axis, ROW_HEIGHT * 2.5), pos(axis, bottom)],
+            LineStyle::new().with_thickness(0.2),
+        );
+
+        Ok(rendered(height))
+    }
+}
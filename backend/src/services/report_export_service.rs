@@ -5,15 +5,54 @@
  * - CSV (Comma-Separated Values)
  * - PDF (Portable Document Format)
  * - Excel (XLSX)
+ *
+ * Large exports (e.g. full audit logs) are streamed as CSV into the response
+ * body in chunks instead of being built in memory.
+ *
+ * It also k-anonymizes de-identified research datasets before export.
  */
 
 use anyhow::{Context, Result};
+use axum::body::Body;
 use chrono::Utc;
+use futures::stream::BoxStream;
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
 
 use crate::models::{
-    AppointmentUtilizationReport, DiagnosisTrendsReport, ExportFormat, PatientStatisticsReport,
-    ProviderProductivityReport, RevenueReport,
+    research_export::SUPPRESSED_VALUE, AnonymizationProfile, AnonymizationSummary,
+    AnonymizedDataset, AppointmentReportFilter, AppointmentUtilizationReport,
+    DiagnosisReportFilter, DiagnosisTrendsReport, ExportFormat, ExportReportRequest,
+    PatientReportFilter, PatientStatisticsReport, ProductivityReportFilter,
+    ProviderProductivityReport, QualityIndicatorReport, ReportDateRange, ReportType,
+    ResearchDatasetType, ResearchRecord, RevenueReport, RevenueReportFilter,
 };
+use crate::services::ReportService;
+use crate::utils::EncryptionKey;
+
+/// Quasi-identifiers of a research row: age band, gender, region, period
+type QuasiIdentifiers = [String; 4];
+
+/// Research row during anonymization
+struct AnonymizedRow {
+    subject: Uuid,
+    quasi: QuasiIdentifiers,
+    values: Vec<String>,
+    generalized: bool,
+}
+
+/// Quasi-identifier tuples shared by fewer than `k` distinct patients
+fn small_classes(rows: &[AnonymizedRow], k: usize) -> HashSet<QuasiIdentifiers> {
+    let mut classes: HashMap<&QuasiIdentifiers, HashSet<Uuid>> = HashMap::new();
+    for row in rows {
+        classes.entry(&row.quasi).or_default().insert(row.subject);
+    }
+    classes
+        .into_iter()
+        .filter(|(_, subjects)| subjects.len() < k)
+        .map(|(quasi, _)| quasi.clone())
+        .collect()
+}
 
 /// Export response containing the file data and metadata
 #[derive(Debug)]
@@ -26,6 +65,57 @@ pub struct ExportResponse {
     pub filename: String,
 }
 
+/// Rows of the worksheet between two charts placed beside the tables
+#[cfg(feature = "report-export")]
+const EXCEL_CHART_ROWS: u32 = 16;
+
+/// First and last row of a table whose rows were written from `first` up to
+/// (not including) `next`; None for an empty table, which gets no chart
+#[cfg(feature = "report-export")]
+fn table_rows(first: u32, next: u32) -> Option<(u32, u32)> {
+    (next > first).then(|| (first, next - 1))
+}
+
+/// Native Excel chart of a table of `sheet`: categories from column
+/// `category_col`, one series per (name, value column)
+#[cfg(feature = "report-export")]
+fn excel_chart(
+    chart_type: rust_xlsxwriter::ChartType,
+    title: &str,
+    sheet: &str,
+    (first, last): (u32, u32),
+    category_col: u16,
+    series: &[(&str, u16)],
+) -> rust_xlsxwriter::Chart {
+    let is_pie = matches!(chart_type, rust_xlsxwriter::ChartType::Pie);
+    let mut chart = rust_xlsxwriter::Chart::new(chart_type);
+    chart.title().set_name(title);
+    for (name, col) in series {
+        chart
+            .add_series()
+            .set_name(*name)
+            .set_categories((sheet, first, category_col, last, category_col))
+            .set_values((sheet, first, *col, last, *col));
+    }
+    if series.len() == 1 && !is_pie {
+        chart.legend().set_hidden();
+    }
+    chart
+}
+
+/// Rows written per chunk of a streamed CSV export
+#[cfg(feature = "report-export")]
+const CSV_STREAM_CHUNK_ROWS: usize = 1000;
+
+/// Row of a streamed CSV export
+pub trait CsvRecord {
+    /// Column names, written as the first row
+    fn csv_headers() -> &'static [&'static str];
+
+    /// Field values, in column order
+    fn csv_fields(&self) -> Vec<String>;
+}
+
 /// Report export service
 pub struct ReportExportService;
 
@@ -35,6 +125,76 @@ impl ReportExportService {
         Self
     }
 
+    // ========== STREAMED CSV EXPORT ==========
+
+    /// Stream rows as CSV into a response body
+    ///
+    /// Rows are pulled from `rows` as the client reads the body and sent in
+    /// chunks of 1000 (chunked transfer encoding), so memory use does not grow
+    /// with the export size. An error while reading rows aborts the body, so
+    /// the client sees a failed download rather than a truncated file.
+    #[cfg(feature = "report-export")]
+    pub fn stream_csv<T, E>(rows: BoxStream<'static, Result<T, E>>) -> Result<Body>
+    where
+        T: CsvRecord + Send + 'static,
+        E: std::fmt::Display + Send + 'static,
+    {
+        use axum::body::Bytes;
+        use futures::stream::{self, StreamExt};
+        use std::io;
+
+        let chunks = stream::unfold(Some((rows, true)), |state| async move {
+            let (mut rows, first) = state?;
+            let mut wtr = csv::Writer::from_writer(vec![]);
+
+            if first {
+                if let Err(e) = wtr.write_record(T::csv_headers()) {
+                    return Some((Err(io::Error::other(e.to_string())), None));
+                }
+            }
+
+            // None once the rows are exhausted
+            let mut next_state = None;
+            let mut written = 0;
+            while let Some(row) = rows.next().await {
+                let row = match row {
+                    Ok(row) => row,
+                    Err(e) => {
+                        tracing::error!("Streamed CSV export failed: {}", e);
+                        return Some((Err(io::Error::other(e.to_string())), None));
+                    }
+                };
+                if let Err(e) = wtr.write_record(row.csv_fields()) {
+                    return Some((Err(io::Error::other(e.to_string())), None));
+                }
+
+                written += 1;
+                if written == CSV_STREAM_CHUNK_ROWS {
+                    next_state = Some((rows, false));
+                    break;
+                }
+            }
+
+            match wtr.into_inner() {
+                Ok(buffer) if buffer.is_empty() => None,
+                Ok(buffer) => Some((Ok(Bytes::from(buffer)), next_state)),
+                Err(e) => Some((Err(io::Error::other(e.to_string())), None)),
+            }
+        });
+
+        Ok(Body::from_stream(chunks))
+    }
+
+    /// Stream rows as CSV (unavailable without the report-export feature)
+    #[cfg(not(feature = "report-export"))]
+    pub fn stream_csv<T, E>(_rows: BoxStream<'static, Result<T, E>>) -> Result<Body>
+    where
+        T: CsvRecord + Send + 'static,
+        E: std::fmt::Display + Send + 'static,
+    {
+        anyhow::bail!("Report export feature is not enabled. Rebuild with --features report-export")
+    }
+
     // ========== CSV EXPORT ==========
 
     /// Export appointment utilization report to CSV
@@ -220,11 +380,16 @@ impl ReportExportService {
             .context("Failed to write empty row")?;
         wtr.write_record(["Age Distribution"])
             .context("Failed to write section header")?;
-        wtr.write_record(["Age Group", "Count"])
+        wtr.write_record(["Age Group", "Count", "Male", "Female"])
             .context("Failed to write column headers")?;
         for age_group in &report.age_distribution {
-            wtr.write_record([&age_group.age_group, &age_group.count.to_string()])
-                .context("Failed to write record")?;
+            wtr.write_record([
+                &age_group.age_group,
+                &age_group.count.to_string(),
+                &age_group.male.to_string(),
+                &age_group.female.to_string(),
+            ])
+            .context("Failed to write record")?;
         }
 
         // Monthly registrations
@@ -402,6 +567,16 @@ impl ReportExportService {
             &format!("{:.1}", report.summary.avg_appointment_duration),
         ])
         .context("Failed to write record")?;
+        wtr.write_record([
+            "Avg Wait (min)",
+            &format!("{:.1}", report.summary.avg_wait_minutes),
+        ])
+        .context("Failed to write record")?;
+        wtr.write_record([
+            "Median Wait (min)",
+            &format!("{:.1}", report.summary.median_wait_minutes),
+        ])
+        .context("Failed to write record")?;
 
         // Provider breakdown
         wtr.write_record([""])
@@ -418,6 +593,7 @@ impl ReportExportService {
             "Unique Patients",
             "Avg Visits/Day",
             "Completion Rate (%)",
+            "Avg Wait (min)",
         ])
         .context("Failed to write column headers")?;
         for provider in &report.by_provider {
@@ -431,6 +607,7 @@ impl ReportExportService {
                 &provider.unique_patients_seen.to_string(),
                 &format!("{:.2}", provider.avg_visits_per_day),
                 &format!("{:.1}", provider.completion_rate),
+                &format!("{:.1}", provider.avg_wait_minutes),
             ])
             .context("Failed to write record")?;
         }
@@ -512,6 +689,59 @@ impl ReportExportService {
         })
     }
 
+    /// Export GP quality indicators in the regional CSV layout
+    ///
+    /// Semicolon-separated with one row per indicator, dates as DD/MM/YYYY
+    /// and percentages with a decimal comma.
+    #[cfg(feature = "report-export")]
+    pub fn export_quality_indicators_csv(
+        &self,
+        report: &QualityIndicatorReport,
+    ) -> Result<ExportResponse> {
+        let mut wtr = csv::WriterBuilder::new()
+            .delimiter(b';')
+            .from_writer(vec![]);
+
+        wtr.write_record([
+            "CODICE_INDICATORE",
+            "DESCRIZIONE",
+            "NUMERATORE",
+            "DENOMINATORE",
+            "VALORE_PERCENTUALE",
+            "DATA_INIZIO",
+            "DATA_FINE",
+        ])
+        .context("Failed to write CSV header")?;
+
+        let start_date = report.date_range.start_date.format("%d/%m/%Y").to_string();
+        let end_date = report.date_range.end_date.format("%d/%m/%Y").to_string();
+        for indicator in &report.indicators {
+            wtr.write_record([
+                indicator.code.as_str(),
+                indicator.description.as_str(),
+                &indicator.numerator.to_string(),
+                &indicator.denominator.to_string(),
+                &format!("{:.2}", indicator.percentage).replace('.', ","),
+                &start_date,
+                &end_date,
+            ])
+            .context("Failed to write record")?;
+        }
+
+        let data = wtr.into_inner().context("Failed to finalize CSV")?;
+        let filename = format!(
+            "indicatori_qualita_{}_{}.csv",
+            report.date_range.start_date.format("%Y%m%d"),
+            report.date_range.end_date.format("%Y%m%d")
+        );
+
+        Ok(ExportResponse {
+            data,
+            content_type: "text/csv; charset=utf-8".to_string(),
+            filename,
+        })
+    }
+
     // ========== EXCEL EXPORT ==========
 
     /// Export appointment utilization report to Excel
@@ -520,7 +750,9 @@ impl ReportExportService {
         &self,
         report: &AppointmentUtilizationReport,
     ) -> Result<ExportResponse> {
-        use rust_xlsxwriter::{Format, Workbook};
+        use rust_xlsxwriter::{ChartType, Format, Workbook};
+
+        const SHEET: &str = "Appointment Report";
 
         let mut workbook = Workbook::new();
 
@@ -531,7 +763,7 @@ impl ReportExportService {
 
         // Add worksheet and get its index
         let worksheet = workbook.add_worksheet();
-        worksheet.set_name("Appointment Report")?;
+        worksheet.set_name(SHEET)?;
 
         // Title
         let mut row = 0u32;
@@ -585,11 +817,13 @@ impl ReportExportService {
         worksheet.write_with_format(row, 0, "Type", &header_format)?;
         worksheet.write_with_format(row, 1, "Count", &header_format)?;
         row += 1;
+        let type_first = row;
         for (appt_type, count) in &report.by_type {
             worksheet.write(row, 0, appt_type)?;
             worksheet.write(row, 1, *count as f64)?;
             row += 1;
         }
+        let type_rows = table_rows(type_first, row);
         row += 1;
 
         // By day of week
@@ -598,11 +832,13 @@ impl ReportExportService {
         worksheet.write_with_format(row, 0, "Day", &header_format)?;
         worksheet.write_with_format(row, 1, "Count", &header_format)?;
         row += 1;
+        let day_first = row;
         for day in &report.by_day_of_week {
             worksheet.write(row, 0, &day.day_name)?;
             worksheet.write(row, 1, day.count as f64)?;
             row += 1;
         }
+        let day_rows = table_rows(day_first, row);
         row += 1;
 
         // By hour
@@ -611,11 +847,13 @@ impl ReportExportService {
         worksheet.write_with_format(row, 0, "Hour", &header_format)?;
         worksheet.write_with_format(row, 1, "Count", &header_format)?;
         row += 1;
+        let hour_first = row;
         for hour in &report.by_hour {
             worksheet.write(row, 0, format!("{:02}:00", hour.hour))?;
             worksheet.write(row, 1, hour.count as f64)?;
             row += 1;
         }
+        let hour_rows = table_rows(hour_first, row);
         row += 1;
 
         // Daily trend (in the same sheet)
@@ -628,6 +866,7 @@ impl ReportExportService {
         worksheet.write_with_format(row, 4, "No Shows", &header_format)?;
         row += 1;
 
+        let trend_first = row;
         for day in &report.daily_trend {
             worksheet.write(row, 0, day.date.to_string())?;
             worksheet.write(row, 1, day.scheduled as f64)?;
@@ -636,6 +875,58 @@ impl ReportExportService {
             worksheet.write(row, 4, day.no_shows as f64)?;
             row += 1;
         }
+        let trend_rows = table_rows(trend_first, row);
+
+        // Charts beside the tables (column G)
+        let mut charts = Vec::new();
+        if let Some(rows) = trend_rows {
+            charts.push(excel_chart(
+                ChartType::Line,
+                "Daily Trend",
+                SHEET,
+                rows,
+                0,
+                &[
+                    ("Scheduled", 1),
+                    ("Completed", 2),
+                    ("Cancelled", 3),
+                    ("No Shows", 4),
+                ],
+            ));
+        }
+        if let Some(rows) = type_rows {
+            charts.push(excel_chart(
+                ChartType::Pie,
+                "Appointments by Type",
+                SHEET,
+                rows,
+                0,
+                &[("Count", 1)],
+            ));
+        }
+        if let Some(rows) = day_rows {
+            charts.push(excel_chart(
+                ChartType::Column,
+                "Appointments by Day of Week",
+                SHEET,
+                rows,
+                0,
+                &[("Count", 1)],
+            ));
+        }
+        if let Some(rows) = hour_rows {
+            charts.push(excel_chart(
+                ChartType::Column,
+                "Appointments by Hour",
+                SHEET,
+                rows,
+                0,
+                &[("Count", 1)],
+            ));
+        }
+        for (index, chart) in charts.iter().enumerate() {
+            worksheet.insert_chart(index as u32 * EXCEL_CHART_ROWS, 6, chart)?;
+        }
 
         // Auto-fit columns
         worksheet.set_column_width(0, 25)?;
@@ -661,14 +952,16 @@ impl ReportExportService {
         &self,
         report: &PatientStatisticsReport,
     ) -> Result<ExportResponse> {
-        use rust_xlsxwriter::{Format, Workbook};
+        use rust_xlsxwriter::{Chart, ChartType, Format, Workbook};
+
+        const SHEET: &str = "Patient Statistics";
 
         let mut workbook = Workbook::new();
         let header_format = Format::new().set_bold();
         let title_format = Format::new().set_bold().set_font_size(14);
 
         let worksheet = workbook.add_worksheet();
-        worksheet.set_name("Patient Statistics")?;
+        worksheet.set_name(SHEET)?;
 
         let mut row = 0u32;
         worksheet.write_with_format(row, 0, "Patient Statistics Report", &title_format)?;
@@ -721,6 +1014,7 @@ impl ReportExportService {
         worksheet.write_with_format(row, 0, "Gender", &header_format)?;
         worksheet.write_with_format(row, 1, "Count", &header_format)?;
         row += 1;
+        let gender_first = row;
         worksheet.write(row, 0, "Male")?;
         worksheet.write(row, 1, report.by_gender.male as f64)?;
         row += 1;
@@ -732,6 +1026,7 @@ impl ReportExportService {
         row += 1;
         worksheet.write(row, 0, "Unspecified")?;
         worksheet.write(row, 1, report.by_gender.unspecified as f64)?;
+        let gender_rows = (gender_first, row);
         row += 2;
 
         // Age distribution
@@ -739,12 +1034,20 @@ impl ReportExportService {
         row += 1;
         worksheet.write_with_format(row, 0, "Age Group", &header_format)?;
         worksheet.write_with_format(row, 1, "Count", &header_format)?;
+        worksheet.write_with_format(row, 2, "Male", &header_format)?;
+        worksheet.write_with_format(row, 3, "Female", &header_format)?;
         row += 1;
+        let age_first = row;
         for age in &report.age_distribution {
             worksheet.write(row, 0, &age.age_group)?;
             worksheet.write(row, 1, age.count as f64)?;
+            worksheet.write(row, 2, age.male as f64)?;
+            worksheet.write(row, 3, age.female as f64)?;
+            // Negated male counts (hidden column) draw the left half of the pyramid
+            worksheet.write(row, 4, -(age.male as f64))?;
             row += 1;
         }
+        let age_rows = table_rows(age_first, row);
         row += 1;
 
         // Monthly registrations
@@ -754,15 +1057,62 @@ impl ReportExportService {
         worksheet.write_with_format(row, 1, "Year", &header_format)?;
         worksheet.write_with_format(row, 2, "Count", &header_format)?;
         row += 1;
+        let month_first = row;
         for month in &report.monthly_registrations {
             worksheet.write(row, 0, &month.month_name)?;
             worksheet.write(row, 1, month.year as f64)?;
             worksheet.write(row, 2, month.count as f64)?;
             row += 1;
         }
+        let month_rows = table_rows(month_first, row);
+
+        // Charts beside the tables (column G)
+        let mut charts = vec![excel_chart(
+            ChartType::Pie,
+            "Gender Distribution",
+            SHEET,
+            gender_rows,
+            0,
+            &[("Count", 1)],
+        )];
+        if let Some((first, last)) = age_rows {
+            let mut pyramid = Chart::new(ChartType::Bar);
+            pyramid.title().set_name("Age Pyramid");
+            pyramid
+                .add_series()
+                .set_name("Male")
+                .set_categories((SHEET, first, 0, last, 0))
+                .set_values((SHEET, first, 4, last, 4))
+                .set_overlap(100)
+                .set_gap(10);
+            pyramid
+                .add_series()
+                .set_name("Female")
+                .set_categories((SHEET, first, 0, last, 0))
+                .set_values((SHEET, first, 3, last, 3));
+            // Show the negated male counts as positive numbers
+            pyramid.x_axis().set_num_format("#,##0;#,##0");
+            pyramid.y_axis().set_num_format("#,##0;#,##0");
+            pyramid.show_hidden_data();
+            charts.push(pyramid);
+        }
+        if let Some(rows) = month_rows {
+            charts.push(excel_chart(
+                ChartType::Column,
+                "Monthly Registrations",
+                SHEET,
+                rows,
+                0,
+                &[("Registrations", 2)],
+            ));
+        }
+        for (index, chart) in charts.iter().enumerate() {
+            worksheet.insert_chart(index as u32 * EXCEL_CHART_ROWS, 6, chart)?;
+        }
 
         worksheet.set_column_width(0, 25)?;
         worksheet.set_column_width(1, 15)?;
+        worksheet.set_column_hidden(4)?;
 
         let data = workbook.save_to_buffer()?;
         let filename = format!(
@@ -784,7 +1134,9 @@ impl ReportExportService {
         &self,
         report: &DiagnosisTrendsReport,
     ) -> Result<ExportResponse> {
-        use rust_xlsxwriter::{Format, Workbook};
+        use rust_xlsxwriter::{ChartType, Format, Workbook};
+
+        const SHEET: &str = "Diagnosis Trends";
 
         let mut workbook = Workbook::new();
         let header_format = Format::new().set_bold();
@@ -792,7 +1144,7 @@ impl ReportExportService {
         let percent_format = Format::new().set_num_format("0.00%");
 
         let worksheet = workbook.add_worksheet();
-        worksheet.set_name("Diagnosis Trends")?;
+        worksheet.set_name(SHEET)?;
 
         let mut row = 0u32;
         worksheet.write_with_format(row, 0, "Diagnosis Trends Report", &title_format)?;
@@ -828,6 +1180,7 @@ impl ReportExportService {
         worksheet.write_with_format(row, 2, "Count", &header_format)?;
         worksheet.write_with_format(row, 3, "Percentage", &header_format)?;
         row += 1;
+        let top_first = row;
         for diag in &report.top_diagnoses {
             worksheet.write(row, 0, &diag.icd10_code)?;
             worksheet.write(row, 1, &diag.description)?;
@@ -835,6 +1188,7 @@ impl ReportExportService {
             worksheet.write_with_format(row, 3, diag.percentage / 100.0, &percent_format)?;
             row += 1;
         }
+        let top_rows = table_rows(top_first, row);
         row += 1;
 
         // By category
@@ -844,12 +1198,14 @@ impl ReportExportService {
         worksheet.write_with_format(row, 1, "Category Name", &header_format)?;
         worksheet.write_with_format(row, 2, "Count", &header_format)?;
         row += 1;
+        let category_first = row;
         for cat in &report.by_category {
             worksheet.write(row, 0, &cat.category)?;
             worksheet.write(row, 1, &cat.category_name)?;
             worksheet.write(row, 2, cat.count as f64)?;
             row += 1;
         }
+        let category_rows = table_rows(category_first, row);
         row += 1;
 
         // Monthly trend
@@ -859,12 +1215,50 @@ impl ReportExportService {
         worksheet.write_with_format(row, 1, "Year", &header_format)?;
         worksheet.write_with_format(row, 2, "Count", &header_format)?;
         row += 1;
+        let month_first = row;
         for month in &report.monthly_trend {
             worksheet.write(row, 0, &month.month_name)?;
             worksheet.write(row, 1, month.year as f64)?;
             worksheet.write(row, 2, month.count as f64)?;
             row += 1;
         }
+        let month_rows = table_rows(month_first, row);
+
+        // Charts beside the tables (column F)
+        let mut charts = Vec::new();
+        if let Some(rows) = top_rows {
+            charts.push(excel_chart(
+                ChartType::Bar,
+                "Top Diagnoses",
+                SHEET,
+                rows,
+                0,
+                &[("Count", 2)],
+            ));
+        }
+        if let Some(rows) = category_rows {
+            charts.push(excel_chart(
+                ChartType::Pie,
+                "By ICD-10 Category",
+                SHEET,
+                rows,
+                1,
+                &[("Count", 2)],
+            ));
+        }
+        if let Some(rows) = month_rows {
+            charts.push(excel_chart(
+                ChartType::Line,
+                "Monthly Trend",
+                SHEET,
+                rows,
+                0,
+                &[("Diagnoses", 2)],
+            ));
+        }
+        for (index, chart) in charts.iter().enumerate() {
+            worksheet.insert_chart(index as u32 * EXCEL_CHART_ROWS, 5, chart)?;
+        }
 
         worksheet.set_column_width(0, 15)?;
         worksheet.set_column_width(1, 40)?;
@@ -937,6 +1331,12 @@ impl ReportExportService {
         row += 1;
         worksheet.write(row, 0, "Avg Appointment Duration (min)")?;
         worksheet.write(row, 1, report.summary.avg_appointment_duration)?;
+        row += 1;
+        worksheet.write(row, 0, "Avg Wait (min)")?;
+        worksheet.write(row, 1, report.summary.avg_wait_minutes)?;
+        row += 1;
+        worksheet.write(row, 0, "Median Wait (min)")?;
+        worksheet.write(row, 1, report.summary.median_wait_minutes)?;
         row += 2;
 
         // Provider details
@@ -952,6 +1352,7 @@ impl ReportExportService {
             "Unique Patients",
             "Avg Visits/Day",
             "Completion Rate",
+            "Avg Wait (min)",
         ];
         for (col, header) in headers.iter().enumerate() {
             worksheet.write_with_format(row, col as u16, *header, &header_format)?;
@@ -968,6 +1369,7 @@ impl ReportExportService {
             worksheet.write(row, 6, provider.unique_patients_seen as f64)?;
             worksheet.write(row, 7, provider.avg_visits_per_day)?;
             worksheet.write_with_format(row, 8, provider.completion_rate / 100.0, &percent_format)?;
+            worksheet.write(row, 9, provider.avg_wait_minutes)?;
             row += 1;
         }
 
@@ -1073,6 +1475,8 @@ impl ReportExportService {
             fonts, Document, Element, SimplePageDecorator,
         };
 
+        use crate::services::report_chart::{BarChart, LineChart};
+
         // Load built-in font
         let font_family = fonts::from_files("./assets/fonts", "LiberationSans", None)
             .or_else(|_| fonts::from_files("/usr/share/fonts/liberation", "LiberationSans", None))
@@ -1191,6 +1595,50 @@ impl ReportExportService {
         }
         doc.push(type_table);
 
+        // Charts
+        let trend = &report.daily_trend;
+        doc.push(Break::new(1));
+        doc.push(
+            LineChart::new(
+                "Daily Trend",
+                trend.iter().map(|day| day.date.to_string()).collect(),
+            )
+            .series("Scheduled", trend.iter().map(|day| day.scheduled as f64).collect())
+            .series("Completed", trend.iter().map(|day| day.completed as f64).collect())
+            .series("Cancelled", trend.iter().map(|day| day.cancelled as f64).collect())
+            .series("No Shows", trend.iter().map(|day| day.no_shows as f64).collect()),
+        );
+        doc.push(Break::new(1));
+        doc.push(
+            BarChart::new(
+                "Appointments by Type",
+                report
+                    .by_type
+                    .iter()
+                    .map(|(appt_type, count)| (appt_type.clone(), *count as f64))
+                    .collect(),
+            )
+            .with_shares(),
+        );
+        doc.push(Break::new(1));
+        doc.push(BarChart::new(
+            "Appointments by Day of Week",
+            report
+                .by_day_of_week
+                .iter()
+                .map(|day| (day.day_name.clone(), day.count as f64))
+                .collect(),
+        ));
+        doc.push(Break::new(1));
+        doc.push(BarChart::new(
+            "Appointments by Hour",
+            report
+                .by_hour
+                .iter()
+                .map(|hour| (format!("{:02}:00", hour.hour), hour.count as f64))
+                .collect(),
+        ));
+
         // Render to buffer
         let mut buffer = Vec::new();
         doc.render(&mut buffer)
@@ -1219,6 +1667,8 @@ impl ReportExportService {
             fonts, Document, Element, SimplePageDecorator,
         };
 
+        use crate::services::report_chart::{BarChart, PyramidChart};
+
         let font_family = fonts::from_files("./assets/fonts", "LiberationSans", None)
             .or_else(|_| fonts::from_files("/usr/share/fonts/liberation", "LiberationSans", None))
             .or_else(|_| {
@@ -1325,6 +1775,31 @@ impl ReportExportService {
             .expect("Failed to push row");
         doc.push(gender_table);
 
+        // Charts
+        doc.push(Break::new(1));
+        doc.push(PyramidChart::new(
+            "Age Pyramid",
+            report
+                .age_distribution
+                .iter()
+                .map(|age| (age.age_group.clone(), age.male as f64, age.female as f64))
+                .collect(),
+        ));
+        doc.push(Break::new(1));
+        doc.push(BarChart::new(
+            "Monthly Registrations",
+            report
+                .monthly_registrations
+                .iter()
+                .map(|month| {
+                    (
+                        format!("{} {}", month.month_name, month.year),
+                        month.count as f64,
+                    )
+                })
+                .collect(),
+        ));
+
         let mut buffer = Vec::new();
         doc.render(&mut buffer)
             .context("Failed to render PDF")?;
@@ -1352,6 +1827,8 @@ impl ReportExportService {
             fonts, Document, Element, SimplePageDecorator,
         };
 
+        use crate::services::report_chart::{BarChart, LineChart};
+
         let font_family = fonts::from_files("./assets/fonts", "LiberationSans", None)
             .or_else(|_| fonts::from_files("/usr/share/fonts/liberation", "LiberationSans", None))
             .or_else(|_| {
@@ -1414,6 +1891,39 @@ impl ReportExportService {
         }
         doc.push(diag_table);
 
+        // Charts
+        doc.push(Break::new(1));
+        doc.push(
+            BarChart::new(
+                "By ICD-10 Category",
+                report
+                    .by_category
+                    .iter()
+                    .map(|cat| (cat.category_name.clone(), cat.count as f64))
+                    .collect(),
+            )
+            .with_shares(),
+        );
+        doc.push(Break::new(1));
+        doc.push(
+            LineChart::new(
+                "Monthly Trend",
+                report
+                    .monthly_trend
+                    .iter()
+                    .map(|month| format!("{} {}", month.month_name, month.year))
+                    .collect(),
+            )
+            .series(
+                "Diagnoses",
+                report
+                    .monthly_trend
+                    .iter()
+                    .map(|month| month.count as f64)
+                    .collect(),
+            ),
+        );
+
         let mut buffer = Vec::new();
         doc.render(&mut buffer)
             .context("Failed to render PDF")?;
@@ -1513,6 +2023,12 @@ impl ReportExportService {
             .element(Paragraph::new(report.summary.total_documents.to_string()))
             .push()
             .expect("Failed to push row");
+        summary_table
+            .row()
+            .element(Paragraph::new("Avg Wait (min)"))
+            .element(Paragraph::new(format!("{:.1}", report.summary.avg_wait_minutes)))
+            .push()
+            .expect("Failed to push row");
         doc.push(summary_table);
         doc.push(Break::new(1));
 
@@ -1647,6 +2163,216 @@ impl ReportExportService {
         })
     }
 
+    // ========== RESEARCH DATASETS ==========
+
+    /// k-anonymize de-identified research records
+    ///
+    /// Quasi-identifiers are mapped to the profile's bands and periods. Rows
+    /// in classes with fewer than `k` distinct patients are generalized
+    /// step by step (region dropped, then period widened to the year), and
+    /// rows that still fall short are suppressed.
+    pub fn anonymize_research_records(
+        &self,
+        dataset: ResearchDatasetType,
+        records: Vec<ResearchRecord>,
+        profile: &AnonymizationProfile,
+        date_range: ReportDateRange,
+        unreadable_records: usize,
+    ) -> AnonymizedDataset {
+        let k = profile.k.max(1) as usize;
+        let source_records = records.len();
+
+        let mut rows: Vec<AnonymizedRow> = records
+            .into_iter()
+            .map(|record| {
+                let gender = if profile.include_gender {
+                    record.gender
+                } else {
+                    SUPPRESSED_VALUE.to_string()
+                };
+                let region = match record.region {
+                    Some(region) if profile.include_region && !region.trim().is_empty() => {
+                        region.trim().to_uppercase()
+                    }
+                    _ => SUPPRESSED_VALUE.to_string(),
+                };
+                let period = match (dataset, record.event_date) {
+                    (ResearchDatasetType::Diagnoses, Some(date)) => profile.period(date),
+                    _ => SUPPRESSED_VALUE.to_string(),
+                };
+
+                AnonymizedRow {
+                    subject: record.subject,
+                    quasi: [profile.age_band(record.age), gender, region, period],
+                    values: record.values,
+                    generalized: false,
+                }
+            })
+            .collect();
+
+        // Generalization steps: drop the region, then keep only the year
+        let steps: [fn(&mut QuasiIdentifiers) -> bool; 2] = [
+            |quasi| {
+                let changed = quasi[2] != SUPPRESSED_VALUE;
+                quasi[2] = SUPPRESSED_VALUE.to_string();
+                changed
+            },
+            |quasi| {
+                let year: String = quasi[3].chars().take(4).collect();
+                let changed = quasi[3] != year;
+                quasi[3] = year;
+                changed
+            },
+        ];
+
+        for step in steps {
+            let small = small_classes(&rows, k);
+            if small.is_empty() {
+                break;
+            }
+            for row in rows.iter_mut().filter(|row| small.contains(&row.quasi)) {
+                if step(&mut row.quasi) {
+                    row.generalized = true;
+                }
+            }
+        }
+
+        let small = small_classes(&rows, k);
+        rows.retain(|row| !small.contains(&row.quasi));
+
+        let mut class_sizes: HashMap<&QuasiIdentifiers, HashSet<Uuid>> = HashMap::new();
+        for row in &rows {
+            class_sizes.entry(&row.quasi).or_default().insert(row.subject);
+        }
+
+        let summary = AnonymizationSummary {
+            source_records,
+            exported_records: rows.len(),
+            generalized_records: rows.iter().filter(|row| row.generalized).count(),
+            suppressed_records: source_records - rows.len(),
+            equivalence_classes: class_sizes.len(),
+            smallest_class: class_sizes.values().map(HashSet::len).min().unwrap_or(0),
+            unreadable_records,
+        };
+
+        let has_period = dataset == ResearchDatasetType::Diagnoses;
+        let mut output: Vec<Vec<String>> = rows
+            .into_iter()
+            .map(|row| {
+                let [age_band, gender, region, period] = row.quasi;
+                let mut out = vec![age_band, gender, region];
+                if has_period {
+                    out.push(period);
+                }
+                out.extend(row.values);
+                out
+            })
+            .collect();
+        // Sorted so row order says nothing about the source data
+        output.sort();
+
+        AnonymizedDataset {
+            dataset,
+            date_range,
+            generated_at: Utc::now(),
+            profile: profile.clone(),
+            columns: dataset.columns().into_iter().map(str::to_string).collect(),
+            rows: output,
+            summary,
+        }
+    }
+
+    /// Export an anonymized research dataset (CSV, Excel or JSON)
+    #[cfg(feature = "report-export")]
+    pub fn export_research_dataset(
+        &self,
+        dataset: &AnonymizedDataset,
+        format: &ExportFormat,
+    ) -> Result<ExportResponse> {
+        let basename = format!(
+            "research_{}_{}_{}",
+            dataset.dataset.as_str(),
+            dataset.date_range.start_date.format("%Y%m%d"),
+            dataset.date_range.end_date.format("%Y%m%d")
+        );
+
+        match format {
+            ExportFormat::Csv => {
+                let mut wtr = csv::WriterBuilder::new().from_writer(vec![]);
+                wtr.write_record(&dataset.columns)
+                    .context("Failed to write CSV header")?;
+                for row in &dataset.rows {
+                    wtr.write_record(row).context("Failed to write record")?;
+                }
+                let data = wtr.into_inner().context("Failed to finalize CSV")?;
+
+                Ok(ExportResponse {
+                    data,
+                    content_type: "text/csv; charset=utf-8".to_string(),
+                    filename: format!("{}.csv", basename),
+                })
+            }
+            ExportFormat::Excel => {
+                use rust_xlsxwriter::{Format, Workbook};
+
+                let mut workbook = Workbook::new();
+                let header_format = Format::new().set_bold();
+
+                let worksheet = workbook.add_worksheet();
+                worksheet.set_name("Dataset")?;
+                for (col, header) in dataset.columns.iter().enumerate() {
+                    worksheet.write_with_format(0, col as u16, header.as_str(), &header_format)?;
+                }
+                for (index, row) in dataset.rows.iter().enumerate() {
+                    for (col, value) in row.iter().enumerate() {
+                        worksheet.write(index as u32 + 1, col as u16, value.as_str())?;
+                    }
+                }
+
+                // Profile and summary, so the file documents its own de-identification
+                let worksheet = workbook.add_worksheet();
+                worksheet.set_name("Anonymization")?;
+                let profile = serde_json::to_value(&dataset.profile)?;
+                let summary = serde_json::to_value(&dataset.summary)?;
+                let mut row = 0u32;
+                for (section, values) in [("Profile", profile), ("Summary", summary)] {
+                    worksheet.write_with_format(row, 0, section, &header_format)?;
+                    row += 1;
+                    if let serde_json::Value::Object(map) = values {
+                        for (key, value) in map {
+                            worksheet.write(row, 0, key.as_str())?;
+                            worksheet.write(row, 1, value.to_string().trim_matches('"'))?;
+                            row += 1;
+                        }
+                    }
+                    row += 1;
+                }
+                worksheet.set_column_width(0, 24)?;
+
+                let data = workbook.save_to_buffer()?;
+
+                Ok(ExportResponse {
+                    data,
+                    content_type:
+                        "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"
+                            .to_string(),
+                    filename: format!("{}.xlsx", basename),
+                })
+            }
+            ExportFormat::Json => {
+                let data = serde_json::to_vec_pretty(dataset)?;
+                Ok(ExportResponse {
+                    data,
+                    content_type: "application/json".to_string(),
+                    filename: format!("{}.json", basename),
+                })
+            }
+            ExportFormat::Pdf => {
+                anyhow::bail!("Research datasets can be exported as CSV, Excel or JSON")
+            }
+        }
+    }
+
     // ========== PUBLIC EXPORT METHODS ==========
 
     /// Export a report based on format
@@ -1770,6 +2496,105 @@ impl ReportExportService {
         }
     }
 
+    /// Generate a report and export it in the requested format
+    ///
+    /// Shared by the export endpoint and the scheduled report subscriptions.
+    /// The report is computed with the RLS context of `user_id`; the
+    /// dashboard is always exported as JSON.
+    pub async fn generate(
+        &self,
+        report_service: &ReportService,
+        req: &ExportReportRequest,
+        encryption_key: Option<&EncryptionKey>,
+        user_id: Uuid,
+    ) -> Result<ExportResponse> {
+        match req.report_type {
+            ReportType::AppointmentUtilization => {
+                let filter = AppointmentReportFilter {
+                    start_date: req.start_date,
+                    end_date: req.end_date,
+                    provider_id: req.provider_id,
+                    live: req.live,
+                    compare_to: req.compare_to,
+                };
+                let report = report_service
+                    .get_appointment_utilization(filter, user_id)
+                    .await
+                    .context("Failed to generate report")?;
+                self.export_appointment_report(&report, &req.format)
+            }
+            ReportType::PatientStatistics => {
+                let filter = PatientReportFilter {
+                    start_date: req.start_date,
+                    end_date: req.end_date,
+                    live: req.live,
+                    compare_to: req.compare_to,
+                };
+                let report = report_service
+                    .get_patient_statistics(filter, encryption_key, user_id)
+                    .await
+                    .context("Failed to generate report")?;
+                self.export_patient_report(&report, &req.format)
+            }
+            ReportType::DiagnosisTrends => {
+                let filter = DiagnosisReportFilter {
+                    start_date: req.start_date,
+                    end_date: req.end_date,
+                    provider_id: req.provider_id,
+                    limit: None,
+                    live: req.live,
+                    compare_to: req.compare_to,
+                };
+                let report = report_service
+                    .get_diagnosis_trends(filter, user_id)
+                    .await
+                    .context("Failed to generate report")?;
+                self.export_diagnosis_report(&report, &req.format)
+            }
+            ReportType::ProviderProductivity => {
+                let filter = ProductivityReportFilter {
+                    start_date: req.start_date,
+                    end_date: req.end_date,
+                    provider_id: req.provider_id,
+                    compare_to: req.compare_to,
+                };
+                let report = report_service
+                    .get_provider_productivity(filter, user_id)
+                    .await
+                    .context("Failed to generate report")?;
+                self.export_productivity_report(&report, &req.format)
+            }
+            ReportType::Revenue => {
+                let filter = RevenueReportFilter {
+                    start_date: req.start_date,
+                    end_date: req.end_date,
+                    provider_id: req.provider_id,
+                    compare_to: req.compare_to,
+                };
+                let report = report_service
+                    .get_revenue_report(filter, user_id)
+                    .await
+                    .context("Failed to generate report")?;
+                self.export_revenue_report(&report, &req.format)
+            }
+            ReportType::Dashboard => {
+                // Dashboard doesn't support export to other formats - return JSON only
+                let report = report_service
+                    .get_dashboard_report(user_id)
+                    .await
+                    .context("Failed to generate report")?;
+                Ok(ExportResponse {
+                    data: serde_json::to_vec_pretty(&report)?,
+                    content_type: "application/json".to_string(),
+                    filename: format!(
+                        "dashboard_report_{}.json",
+                        Utc::now().format("%Y%m%d_%H%M%S")
+                    ),
+                })
+            }
+        }
+    }
+
     // Fallback implementations when feature is not enabled
     #[cfg(not(feature = "report-export"))]
     pub fn export_appointment_report(
@@ -1815,6 +2640,23 @@ impl ReportExportService {
     ) -> Result<ExportResponse> {
         anyhow::bail!("Report export feature is not enabled. Rebuild with --features report-export")
     }
+
+    #[cfg(not(feature = "report-export"))]
+    pub fn export_research_dataset(
+        &self,
+        _dataset: &AnonymizedDataset,
+        _format: &ExportFormat,
+    ) -> Result<ExportResponse> {
+        anyhow::bail!("Report export feature is not enabled. Rebuild with --features report-export")
+    }
+
+    #[cfg(not(feature = "report-export"))]
+    pub fn export_quality_indicators_csv(
+        &self,
+        _report: &QualityIndicatorReport,
+    ) -> Result<ExportResponse> {
+        anyhow::bail!("Report export feature is not enabled. Rebuild with --features report-export")
+    }
 }
 
 impl Default for ReportExportService {
@@ -1822,3 +2664,145 @@ impl Default for ReportExportService {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn record(subject: Uuid, age: u32, region: &str, month: u32) -> ResearchRecord {
+        ResearchRecord {
+            subject,
+            age: Some(age),
+            gender: "F".to_string(),
+            region: Some(region.to_string()),
+            event_date: NaiveDate::from_ymd_opt(2026, month, 10),
+            values: vec!["E11".to_string(), "PRIMARY".to_string()],
+        }
+    }
+
+    fn date_range() -> ReportDateRange {
+        ReportDateRange {
+            start_date: NaiveDate::from_ymd_opt(2026, 1, 1).unwrap(),
+            end_date: NaiveDate::from_ymd_opt(2026, 12, 31).unwrap(),
+        }
+    }
+
+    fn profile(k: i64) -> AnonymizationProfile {
+        AnonymizationProfile {
+            k,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_anonymize_keeps_classes_of_k_patients() {
+        let records: Vec<ResearchRecord> = (0..3)
+            .map(|i| record(Uuid::new_v4(), 41 + i, "MI", 2))
+            .collect();
+
+        let dataset = ReportExportService::new().anonymize_research_records(
+            ResearchDatasetType::Diagnoses,
+            records,
+            &profile(3),
+            date_range(),
+            0,
+        );
+
+        assert_eq!(dataset.summary.exported_records, 3);
+        assert_eq!(dataset.summary.generalized_records, 0);
+        assert_eq!(dataset.summary.smallest_class, 3);
+        assert_eq!(dataset.rows[0], vec!["40-49", "F", "MI", "2026-Q1", "E11", "PRIMARY"]);
+    }
+
+    #[test]
+    fn test_anonymize_generalizes_before_suppressing() {
+        let mut records: Vec<ResearchRecord> = (0..2)
+            .map(|_| record(Uuid::new_v4(), 45, "MI", 2))
+            .collect();
+        // Different province and quarter: only fits once both are generalized
+        records.push(record(Uuid::new_v4(), 45, "BG", 8));
+        // Different age band: cannot reach k and is suppressed
+        records.push(record(Uuid::new_v4(), 72, "MI", 2));
+
+        let dataset = ReportExportService::new().anonymize_research_records(
+            ResearchDatasetType::Diagnoses,
+            records,
+            &profile(3),
+            date_range(),
+            0,
+        );
+
+        assert_eq!(dataset.summary.source_records, 4);
+        assert_eq!(dataset.summary.exported_records, 3);
+        assert_eq!(dataset.summary.suppressed_records, 1);
+        assert_eq!(dataset.summary.equivalence_classes, 1);
+        assert!(dataset
+            .rows
+            .iter()
+            .all(|row| row[..4] == ["40-49", "F", "*", "2026"]));
+    }
+
+    #[test]
+    fn test_anonymize_counts_patients_not_rows() {
+        // Three diagnoses of a single patient do not make a class of three
+        let subject = Uuid::new_v4();
+        let records: Vec<ResearchRecord> = (0..3).map(|_| record(subject, 45, "MI", 2)).collect();
+
+        let dataset = ReportExportService::new().anonymize_research_records(
+            ResearchDatasetType::Diagnoses,
+            records,
+            &profile(3),
+            date_range(),
+            0,
+        );
+
+        assert!(dataset.rows.is_empty());
+        assert_eq!(dataset.summary.suppressed_records, 3);
+        assert_eq!(dataset.summary.smallest_class, 0);
+    }
+
+    #[cfg(feature = "report-export")]
+    struct StreamRow(u32, &'static str);
+
+    #[cfg(feature = "report-export")]
+    impl CsvRecord for StreamRow {
+        fn csv_headers() -> &'static [&'static str] {
+            &["id", "label"]
+        }
+
+        fn csv_fields(&self) -> Vec<String> {
+            vec![self.0.to_string(), self.1.to_string()]
+        }
+    }
+
+    #[cfg(feature = "report-export")]
+    #[tokio::test]
+    async fn test_stream_csv_writes_every_row_across_chunks() {
+        use futures::stream::{self, StreamExt};
+
+        let rows: Vec<Result<StreamRow, String>> =
+            (0..2500).map(|i| Ok(StreamRow(i, "a,b"))).collect();
+        let body = ReportExportService::stream_csv(stream::iter(rows).boxed()).unwrap();
+
+        let bytes = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+        let text = String::from_utf8(bytes.to_vec()).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert_eq!(lines.len(), 2501);
+        assert_eq!(lines[0], "id,label");
+        assert_eq!(lines[1], "0,\"a,b\"");
+        assert_eq!(lines[2500], "2499,\"a,b\"");
+    }
+
+    #[cfg(feature = "report-export")]
+    #[tokio::test]
+    async fn test_stream_csv_aborts_on_row_error() {
+        use futures::stream::{self, StreamExt};
+
+        let rows = vec![Ok(StreamRow(1, "x")), Err("connection lost".to_string())];
+        let body = ReportExportService::stream_csv(stream::iter(rows).boxed()).unwrap();
+
+        assert!(axum::body::to_bytes(body, usize::MAX).await.is_err());
+    }
+}
@@ -3,28 +3,84 @@
  *
  * Business logic for reporting and analytics, including:
  * - Appointment utilization reports
+ * - Appointment weekday x hour heatmap
  * - Patient statistics
  * - Diagnosis trends
  * - Provider productivity metrics
  * - Revenue tracking (optional)
  * - Dashboard aggregation
+ * - De-identified records for research dataset exports
+ *
+ * Appointment utilization, patient registrations and diagnosis trends are
+ * aggregated from daily rollups. The rollups are read from materialized views
+ * (see `report_view_service`) while the views are fresh, and computed live
+ * when they are stale or the caller asks for live data.
  */
 
+use crate::db::rls::begin_with_rls;
 use crate::models::{
-    AgeGroupCount, AppointmentReportFilter, AppointmentUtilizationReport, DailyAppointmentCount,
-    DashboardReport, DayOfWeekCount, DiagnosisCategoryCount, DiagnosisCount, DiagnosisReportFilter,
-    DiagnosisTrendsReport, GenderBreakdown, HourlyCount, MonthlyCount, MonthlyDiagnosisCount,
-    NewPatientSummary, PatientReportFilter, PatientStatisticsReport, ProductivityReportFilter,
+    AgeGroupCount, AppointmentHeatmapReport, AppointmentReportFilter,
+    AppointmentUtilizationReport, DailyAppointmentCount, DashboardReport, DayOfWeekCount, DiagnosisCategoryCount, DiagnosisCount, DiagnosisReportFilter,
+    DiagnosisTrendsReport, GenderBreakdown, HeatmapSlotCount, HourlyCount, MonthlyCount, MonthlyDiagnosisCount,
+    NewPatientSummary, PatientReportFilter, PeriodComparison, PatientStatisticsReport, ProductivityReportFilter,
     ProductivitySummary, ProviderProductivity, ProviderProductivityReport, QuickStats,
     RecentActivity, RecentAppointment, RecentVisit, ReportDateRange, RevenueReport,
-    RevenueReportFilter,
+    RevenueReportFilter, AnonymizationProfile, ResearchDatasetType, ResearchRecord,
+    SatisfactionSummary, net_promoter_score,
+};
+use crate::models::patient::Address;
+use crate::services::report_view_service::{
+    APPOINTMENT_STATS_VIEW, DIAGNOSIS_STATS_VIEW, PATIENT_REGISTRATIONS_VIEW,
 };
+use crate::utils::EncryptionKey;
 use anyhow::{Context, Result};
-use chrono::{Datelike, NaiveDate, Utc};
-use sqlx::{PgPool, Postgres, Row, Transaction};
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use sqlx::{PgConnection, PgPool, Row};
 use std::collections::HashMap;
 use uuid::Uuid;
 
+/// Days of post-visit surveys summarized on the dashboard
+const DASHBOARD_SATISFACTION_DAYS: i64 = 90;
+
+/// Live equivalent of the report_appointment_stats view
+/// (keep in sync with migration 20260425000001)
+const LIVE_APPOINTMENT_STATS: &str = r#"(
+    SELECT
+        scheduled_start::DATE AS appointment_date,
+        EXTRACT(HOUR FROM scheduled_start)::INTEGER AS hour,
+        provider_id,
+        type::TEXT AS appointment_type,
+        status::TEXT AS status,
+        COUNT(*)::BIGINT AS count
+    FROM appointments
+    GROUP BY 1, 2, 3, 4, 5
+) AS appointment_stats"#;
+
+/// Live equivalent of the report_patient_registrations view
+const LIVE_PATIENT_REGISTRATIONS: &str = r#"(
+    SELECT created_at::DATE AS registration_date, COUNT(*)::BIGINT AS count
+    FROM patients
+    GROUP BY 1
+) AS patient_registrations"#;
+
+/// Live equivalent of the report_diagnosis_stats view
+const LIVE_DIAGNOSIS_STATS: &str = r#"(
+    SELECT v.visit_date, vd.icd10_code, vd.icd10_description, COUNT(*)::BIGINT AS count
+    FROM visit_diagnoses vd
+    JOIN visits v ON vd.visit_id = v.id
+    GROUP BY 1, 2, 3
+) AS diagnosis_stats"#;
+
+/// Age groups of the patient statistics report (label, highest age)
+const AGE_GROUPS: [(&str, u32); 6] = [
+    ("0-18", 18),
+    ("19-30", 30),
+    ("31-50", 50),
+    ("51-65", 65),
+    ("66-80", 80),
+    ("80+", u32::MAX),
+];
+
 /// Report service for generating analytics and statistics
 pub struct ReportService {
     pool: PgPool,
@@ -36,34 +92,28 @@ impl ReportService {
         Self { pool }
     }
 
-    /// Helper to set RLS context in a transaction
+    /// When the data of a report view was computed, if the view may be used
     ///
-    /// This sets the PostgreSQL session variables required by Row-Level Security policies.
-    async fn set_rls_context(
-        tx: &mut Transaction<'_, Postgres>,
-        user_id: Uuid,
-    ) -> Result<()> {
-        // Query the user's role from the database
-        let role: String = sqlx::query_scalar("SELECT role::TEXT FROM users WHERE id = $1")
-            .bind(user_id)
-            .fetch_one(&mut **tx)
-            .await
-            .context("Failed to fetch user role for RLS context")?;
-
-        // Set RLS context variables using set_config() for parameterized queries
-        sqlx::query("SELECT set_config('app.current_user_id', $1, true)")
-            .bind(user_id.to_string())
-            .execute(&mut **tx)
-            .await
-            .context("Failed to set RLS user context")?;
+    /// None when live data was requested or the view is stale (not refreshed
+    /// within two refresh intervals, or never); the report is then computed
+    /// from the live rollup.
+    async fn view_data_as_of(
+        conn: &mut PgConnection,
+        view: &str,
+        live: bool,
+    ) -> Result<Option<DateTime<Utc>>> {
+        if live {
+            return Ok(None);
+        }
 
-        sqlx::query("SELECT set_config('app.current_user_role', $1, true)")
-            .bind(&role)
-            .execute(&mut **tx)
-            .await
-            .context("Failed to set RLS role context")?;
+        let refreshed_at = sqlx::query_scalar(
+            "SELECT refreshed_at FROM report_view_refreshes WHERE view_name = $1 AND stale_after > NOW()",
+        )
+        .bind(view)
+        .fetch_optional(conn)
+        .await?;
 
-        Ok(())
+        Ok(refreshed_at)
     }
 
     /// Get default date range (last 30 days)
@@ -87,6 +137,14 @@ impl ReportService {
         }
     }
 
+    /// Index of the age group (AGE_GROUPS) an age falls in
+    fn age_group_index(age: u32) -> usize {
+        AGE_GROUPS
+            .iter()
+            .position(|(_, max_age)| age <= *max_age)
+            .unwrap_or(AGE_GROUPS.len() - 1)
+    }
+
     /// Get month name
     fn month_name(month: i32) -> String {
         match month {
@@ -138,14 +196,49 @@ impl ReportService {
     ///
     /// Provides comprehensive statistics on appointment scheduling and completion.
     /// When no date range is provided, returns all-time data without date filtering.
+    ///
+    /// With `compare_to` and a date range, the summary metrics are compared
+    /// with the same metrics of that period.
     pub async fn get_appointment_utilization(
         &self,
         filter: AppointmentReportFilter,
         user_id: Uuid,
+    ) -> Result<AppointmentUtilizationReport> {
+        let compare_to = filter.compare_to;
+        let mut report = self.appointment_utilization(filter.clone(), user_id).await?;
+
+        let range = (filter.start_date.is_some() || filter.end_date.is_some())
+            .then(|| report.date_range.clone());
+        if let (Some(compare_to), Some(range)) = (compare_to, range) {
+            let comparison_range = compare_to.range_for(&range);
+            let previous_filter = AppointmentReportFilter {
+                start_date: Some(comparison_range.start_date),
+                end_date: Some(comparison_range.end_date),
+                compare_to: None,
+                ..filter
+            };
+            let previous = self.appointment_utilization(previous_filter, user_id).await?;
+            report.comparison = Some(PeriodComparison::new(
+                compare_to,
+                comparison_range,
+                &report.summary_metrics(),
+                &previous.summary_metrics(),
+            ));
+        }
+
+        Ok(report)
+    }
+
+    /// Compute the appointment utilization report of one period
+    async fn appointment_utilization(
+        &self,
+        filter: AppointmentReportFilter,
+        user_id: Uuid,
     ) -> Result<AppointmentUtilizationReport> {
         // Start transaction for RLS context
-        let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-        Self::set_rls_context(&mut tx, user_id).await?;
+        let mut tx = begin_with_rls(&self.pool, user_id)
+            .await
+            .context("Failed to begin transaction")?;
 
         // Determine if we have a date range (None means "All Time")
         let date_range = match (filter.start_date, filter.end_date) {
@@ -159,20 +252,35 @@ impl ReportService {
         let start_date = date_range.map(|(s, _)| s);
         let end_date = date_range.map(|(_, e)| e);
 
-        // Count by status — fully parameterized, no dynamic SQL
-        let status_row = sqlx::query(
+        // Read the rollup from the view when fresh; only this constant
+        // source is interpolated, filter values are bound
+        let data_as_of = Self::view_data_as_of(
+            &mut tx,
+            APPOINTMENT_STATS_VIEW,
+            filter.live.unwrap_or(false),
+        )
+        .await?;
+        let source = if data_as_of.is_some() {
+            APPOINTMENT_STATS_VIEW
+        } else {
+            LIVE_APPOINTMENT_STATS
+        };
+
+        // Count by status
+        let status_row = sqlx::query(&format!(
             r#"
             SELECT
-                COALESCE(SUM(CASE WHEN status IN ('SCHEDULED', 'CONFIRMED', 'IN_PROGRESS', 'COMPLETED', 'CANCELLED', 'NO_SHOW') THEN 1 ELSE 0 END), 0)::BIGINT as total_scheduled,
-                COALESCE(SUM(CASE WHEN status = 'COMPLETED' THEN 1 ELSE 0 END), 0)::BIGINT as completed,
-                COALESCE(SUM(CASE WHEN status = 'CANCELLED' THEN 1 ELSE 0 END), 0)::BIGINT as cancelled,
-                COALESCE(SUM(CASE WHEN status = 'NO_SHOW' THEN 1 ELSE 0 END), 0)::BIGINT as no_shows
-            FROM appointments
-            WHERE ($1::DATE IS NULL OR scheduled_start::DATE >= $1)
-              AND ($2::DATE IS NULL OR scheduled_start::DATE <= $2)
+                COALESCE(SUM(CASE WHEN status IN ('SCHEDULED', 'CONFIRMED', 'ARRIVED', 'IN_PROGRESS', 'COMPLETED', 'CANCELLED', 'NO_SHOW') THEN count ELSE 0 END), 0)::BIGINT as total_scheduled,
+                COALESCE(SUM(CASE WHEN status = 'COMPLETED' THEN count ELSE 0 END), 0)::BIGINT as completed,
+                COALESCE(SUM(CASE WHEN status = 'CANCELLED' THEN count ELSE 0 END), 0)::BIGINT as cancelled,
+                COALESCE(SUM(CASE WHEN status = 'NO_SHOW' THEN count ELSE 0 END), 0)::BIGINT as no_shows
+            FROM {}
+            WHERE ($1::DATE IS NULL OR appointment_date >= $1)
+              AND ($2::DATE IS NULL OR appointment_date <= $2)
               AND ($3::UUID IS NULL OR provider_id = $3)
             "#,
-        )
+            source
+        ))
         .bind(start_date)
         .bind(end_date)
         .bind(filter.provider_id)
@@ -213,9 +321,10 @@ impl ReportService {
             }
         } else {
             // For All Time, get date range from actual data
-            let date_range_row: Option<(Option<NaiveDate>, Option<NaiveDate>)> = sqlx::query_as(
-                "SELECT MIN(scheduled_start::DATE), MAX(scheduled_start::DATE) FROM appointments"
-            )
+            let date_range_row: Option<(Option<NaiveDate>, Option<NaiveDate>)> = sqlx::query_as(&format!(
+                "SELECT MIN(appointment_date), MAX(appointment_date) FROM {}",
+                source
+            ))
             .fetch_optional(&mut *tx)
             .await?;
 
@@ -228,16 +337,17 @@ impl ReportService {
         };
 
         // Get breakdown by type
-        let type_rows = sqlx::query(
+        let type_rows = sqlx::query(&format!(
             r#"
-            SELECT type::TEXT as appointment_type, COUNT(*)::BIGINT as count
-            FROM appointments
-            WHERE ($1::DATE IS NULL OR scheduled_start::DATE >= $1)
-              AND ($2::DATE IS NULL OR scheduled_start::DATE <= $2)
+            SELECT appointment_type, SUM(count)::BIGINT as count
+            FROM {}
+            WHERE ($1::DATE IS NULL OR appointment_date >= $1)
+              AND ($2::DATE IS NULL OR appointment_date <= $2)
               AND ($3::UUID IS NULL OR provider_id = $3)
-            GROUP BY type
+            GROUP BY appointment_type
             "#,
-        )
+            source
+        ))
         .bind(start_date)
         .bind(end_date)
         .bind(filter.provider_id)
@@ -252,17 +362,18 @@ impl ReportService {
         }
 
         // Get breakdown by day of week (PostgreSQL: 0=Sunday, 6=Saturday)
-        let dow_rows = sqlx::query(
+        let dow_rows = sqlx::query(&format!(
             r#"
-            SELECT EXTRACT(DOW FROM scheduled_start)::INTEGER as dow, COUNT(*)::BIGINT as count
-            FROM appointments
-            WHERE ($1::DATE IS NULL OR scheduled_start::DATE >= $1)
-              AND ($2::DATE IS NULL OR scheduled_start::DATE <= $2)
+            SELECT EXTRACT(DOW FROM appointment_date)::INTEGER as dow, SUM(count)::BIGINT as count
+            FROM {}
+            WHERE ($1::DATE IS NULL OR appointment_date >= $1)
+              AND ($2::DATE IS NULL OR appointment_date <= $2)
               AND ($3::UUID IS NULL OR provider_id = $3)
             GROUP BY dow
             ORDER BY dow
             "#,
-        )
+            source
+        ))
         .bind(start_date)
         .bind(end_date)
         .bind(filter.provider_id)
@@ -281,17 +392,18 @@ impl ReportService {
         }
 
         // Get breakdown by hour
-        let hour_rows = sqlx::query(
+        let hour_rows = sqlx::query(&format!(
             r#"
-            SELECT EXTRACT(HOUR FROM scheduled_start)::INTEGER as hour, COUNT(*)::BIGINT as count
-            FROM appointments
-            WHERE ($1::DATE IS NULL OR scheduled_start::DATE >= $1)
-              AND ($2::DATE IS NULL OR scheduled_start::DATE <= $2)
+            SELECT hour, SUM(count)::BIGINT as count
+            FROM {}
+            WHERE ($1::DATE IS NULL OR appointment_date >= $1)
+              AND ($2::DATE IS NULL OR appointment_date <= $2)
               AND ($3::UUID IS NULL OR provider_id = $3)
             GROUP BY hour
             ORDER BY hour
             "#,
-        )
+            source
+        ))
         .bind(start_date)
         .bind(end_date)
         .bind(filter.provider_id)
@@ -306,22 +418,23 @@ impl ReportService {
         }
 
         // Get daily trend
-        let daily_rows = sqlx::query(
+        let daily_rows = sqlx::query(&format!(
             r#"
             SELECT
-                scheduled_start::DATE as date,
-                COUNT(*)::BIGINT as scheduled,
-                SUM(CASE WHEN status = 'COMPLETED' THEN 1 ELSE 0 END)::BIGINT as completed,
-                SUM(CASE WHEN status = 'CANCELLED' THEN 1 ELSE 0 END)::BIGINT as cancelled,
-                SUM(CASE WHEN status = 'NO_SHOW' THEN 1 ELSE 0 END)::BIGINT as no_shows
-            FROM appointments
-            WHERE ($1::DATE IS NULL OR scheduled_start::DATE >= $1)
-              AND ($2::DATE IS NULL OR scheduled_start::DATE <= $2)
+                appointment_date as date,
+                SUM(count)::BIGINT as scheduled,
+                SUM(CASE WHEN status = 'COMPLETED' THEN count ELSE 0 END)::BIGINT as completed,
+                SUM(CASE WHEN status = 'CANCELLED' THEN count ELSE 0 END)::BIGINT as cancelled,
+                SUM(CASE WHEN status = 'NO_SHOW' THEN count ELSE 0 END)::BIGINT as no_shows
+            FROM {}
+            WHERE ($1::DATE IS NULL OR appointment_date >= $1)
+              AND ($2::DATE IS NULL OR appointment_date <= $2)
               AND ($3::UUID IS NULL OR provider_id = $3)
-            GROUP BY scheduled_start::DATE
+            GROUP BY appointment_date
             ORDER BY date
             "#,
-        )
+            source
+        ))
         .bind(start_date)
         .bind(end_date)
         .bind(filter.provider_id)
@@ -377,22 +490,177 @@ impl ReportService {
             by_day_of_week,
             by_hour,
             daily_trend,
+            data_as_of,
+            comparison: None,
         })
     }
 
+    // ========== APPOINTMENT HEATMAP ==========
+
+    /// Generate the weekday x hour booking and no-show heatmap
+    ///
+    /// Defaults to the last 30 days when no date range is provided. All slots
+    /// are computed in a single grouped query.
+    pub async fn get_appointment_heatmap(
+        &self,
+        filter: AppointmentReportFilter,
+        user_id: Uuid,
+    ) -> Result<AppointmentHeatmapReport> {
+        let (start_date, end_date) = match (filter.start_date, filter.end_date) {
+            (Some(s), Some(e)) => (s, e),
+            (Some(s), None) => (s, Utc::now().date_naive()),
+            (None, Some(e)) => (e - chrono::Duration::days(30), e),
+            (None, None) => Self::default_date_range(),
+        };
+
+        let mut tx = begin_with_rls(&self.pool, user_id)
+            .await
+            .context("Failed to begin transaction")?;
+
+        let slots = sqlx::query_as::<_, HeatmapSlotCount>(
+            r#"
+            SELECT
+                EXTRACT(DOW FROM scheduled_start)::INTEGER as day,
+                EXTRACT(HOUR FROM scheduled_start)::INTEGER as hour,
+                COUNT(*)::BIGINT as bookings,
+                COUNT(*) FILTER (WHERE status = 'NO_SHOW')::BIGINT as no_shows
+            FROM appointments
+            WHERE scheduled_start::DATE >= $1
+              AND scheduled_start::DATE <= $2
+              AND status != 'CANCELLED'
+              AND ($3::UUID IS NULL OR provider_id = $3)
+            GROUP BY day, hour
+            "#,
+        )
+        .bind(start_date)
+        .bind(end_date)
+        .bind(filter.provider_id)
+        .fetch_all(&mut *tx)
+        .await
+        .context("Failed to load appointment heatmap")?;
+
+        tx.commit().await.context("Failed to commit transaction")?;
+
+        Ok(Self::build_heatmap(&slots, start_date, end_date))
+    }
+
+    /// Number of times each weekday (0=Sunday) occurs in an inclusive date range
+    fn weekday_occurrences(start: NaiveDate, end: NaiveDate) -> [i64; 7] {
+        let mut occurrences = [0i64; 7];
+        if end < start {
+            return occurrences;
+        }
+
+        let days = (end - start).num_days() + 1;
+        let full_weeks = days / 7;
+        occurrences.iter_mut().for_each(|o| *o = full_weeks);
+
+        let first = start.weekday().num_days_from_sunday() as i64;
+        for offset in 0..(days % 7) {
+            occurrences[((first + offset) % 7) as usize] += 1;
+        }
+
+        occurrences
+    }
+
+    /// Arrange slot counts into weekday x hour matrices
+    fn build_heatmap(
+        slots: &[HeatmapSlotCount],
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+    ) -> AppointmentHeatmapReport {
+        let mut bookings = vec![vec![0i64; 24]; 7];
+        let mut no_shows = vec![vec![0i64; 24]; 7];
+
+        for slot in slots {
+            if (0..7).contains(&slot.day) && (0..24).contains(&slot.hour) {
+                bookings[slot.day as usize][slot.hour as usize] += slot.bookings;
+                no_shows[slot.day as usize][slot.hour as usize] += slot.no_shows;
+            }
+        }
+
+        let occurrences = Self::weekday_occurrences(start_date, end_date);
+        let density = |matrix: &[Vec<i64>]| -> Vec<Vec<f64>> {
+            matrix
+                .iter()
+                .zip(occurrences.iter())
+                .map(|(row, &count)| {
+                    row.iter()
+                        .map(|&value| if count > 0 { value as f64 / count as f64 } else { 0.0 })
+                        .collect()
+                })
+                .collect()
+        };
+
+        AppointmentHeatmapReport {
+            date_range: ReportDateRange {
+                start_date,
+                end_date,
+            },
+            days: (0..7).map(Self::day_of_week_name).collect(),
+            booking_density: density(&bookings),
+            no_show_density: density(&no_shows),
+            max_bookings: bookings.iter().flatten().copied().max().unwrap_or(0),
+            total_bookings: bookings.iter().flatten().sum(),
+            total_no_shows: no_shows.iter().flatten().sum(),
+            bookings,
+            no_shows,
+        }
+    }
+
     // ========== PATIENT STATISTICS REPORT ==========
 
     /// Generate patient statistics report
     ///
-    /// Provides demographics and registration statistics.
+    /// Provides demographics and registration statistics. The age
+    /// distribution is only computed when `encryption_key` is given.
+    ///
+    /// With `compare_to` and a date range, the summary metrics are compared
+    /// with the same metrics of that period.
     pub async fn get_patient_statistics(
         &self,
         filter: PatientReportFilter,
+        encryption_key: Option<&EncryptionKey>,
+        user_id: Uuid,
+    ) -> Result<PatientStatisticsReport> {
+        let compare_to = filter.compare_to;
+        let mut report = self
+            .patient_statistics(filter.clone(), encryption_key, user_id)
+            .await?;
+
+        let range = report.date_range.clone();
+        if let (Some(compare_to), Some(range)) = (compare_to, range) {
+            let comparison_range = compare_to.range_for(&range);
+            let previous_filter = PatientReportFilter {
+                start_date: Some(comparison_range.start_date),
+                end_date: Some(comparison_range.end_date),
+                compare_to: None,
+                ..filter
+            };
+            // Only registrations are compared; skip decrypting dates of birth
+            let previous = self.patient_statistics(previous_filter, None, user_id).await?;
+            report.comparison = Some(PeriodComparison::new(
+                compare_to,
+                comparison_range,
+                &report.summary_metrics(),
+                &previous.summary_metrics(),
+            ));
+        }
+
+        Ok(report)
+    }
+
+    /// Compute the patient statistics report of one period
+    async fn patient_statistics(
+        &self,
+        filter: PatientReportFilter,
+        encryption_key: Option<&EncryptionKey>,
         user_id: Uuid,
     ) -> Result<PatientStatisticsReport> {
         // Start transaction for RLS context
-        let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-        Self::set_rls_context(&mut tx, user_id).await?;
+        let mut tx = begin_with_rls(&self.pool, user_id)
+            .await
+            .context("Failed to begin transaction")?;
 
         // Total patients by status
         let status_row = sqlx::query(
@@ -421,12 +689,26 @@ impl ReportService {
         .await
         .unwrap_or(0);
 
+        // Registrations are read from the view when fresh
+        let data_as_of = Self::view_data_as_of(
+            &mut tx,
+            PATIENT_REGISTRATIONS_VIEW,
+            filter.live.unwrap_or(false),
+        )
+        .await?;
+        let source = if data_as_of.is_some() {
+            PATIENT_REGISTRATIONS_VIEW
+        } else {
+            LIVE_PATIENT_REGISTRATIONS
+        };
+
         // New patients in period (if date filter provided)
         let (date_range, new_patients_in_period) = match (filter.start_date, filter.end_date) {
             (Some(start), Some(end)) => {
-                let count: i64 = sqlx::query_scalar(
-                    "SELECT COUNT(*)::BIGINT FROM patients WHERE created_at::DATE >= $1 AND created_at::DATE <= $2",
-                )
+                let count: i64 = sqlx::query_scalar(&format!(
+                    "SELECT COALESCE(SUM(count), 0)::BIGINT FROM {} WHERE registration_date >= $1 AND registration_date <= $2",
+                    source
+                ))
                 .bind(start)
                 .bind(end)
                 .fetch_one(&mut *tx)
@@ -465,49 +747,65 @@ impl ReportService {
             unspecified: gender_row.try_get("unspecified").unwrap_or(0),
         };
 
-        // Age distribution (note: date_of_birth is encrypted, so we need to decrypt for calculation)
-        // For now, we'll provide a placeholder since age calculation requires decryption
-        // In production, consider storing age group or calculating during patient creation/update
-        let age_distribution = vec![
-            AgeGroupCount {
-                age_group: "0-18".to_string(),
-                count: 0,
-            },
-            AgeGroupCount {
-                age_group: "19-30".to_string(),
-                count: 0,
-            },
-            AgeGroupCount {
-                age_group: "31-50".to_string(),
-                count: 0,
-            },
-            AgeGroupCount {
-                age_group: "51-65".to_string(),
-                count: 0,
-            },
-            AgeGroupCount {
-                age_group: "66-80".to_string(),
-                count: 0,
-            },
-            AgeGroupCount {
-                age_group: "80+".to_string(),
+        // Age distribution: dates of birth are encrypted, so they are
+        // decrypted in memory when the key is available (counts stay zero
+        // otherwise). Anonymized and deceased patients are left out.
+        let mut age_distribution: Vec<AgeGroupCount> = AGE_GROUPS
+            .iter()
+            .map(|(label, _)| AgeGroupCount {
+                age_group: label.to_string(),
                 count: 0,
-            },
-        ];
+                male: 0,
+                female: 0,
+            })
+            .collect();
+        if let Some(encryption_key) = encryption_key {
+            let patients: Vec<(String, Option<String>)> = sqlx::query_as(
+                r#"
+                SELECT date_of_birth, gender::TEXT
+                FROM patients
+                WHERE anonymized_at IS NULL AND status <> 'DECEASED'
+                "#,
+            )
+            .fetch_all(&mut *tx)
+            .await
+            .context("Failed to fetch dates of birth")?;
+
+            let today = Utc::now().date_naive();
+            for (encrypted_dob, gender) in patients {
+                let dob = encryption_key
+                    .decrypt(&encrypted_dob)
+                    .ok()
+                    .and_then(|dob| NaiveDate::parse_from_str(dob.trim(), "%Y-%m-%d").ok());
+                let Some(dob) = dob else {
+                    continue;
+                };
+                let group = &mut age_distribution[Self::age_group_index(
+                    today.years_since(dob).unwrap_or(0),
+                )];
+                group.count += 1;
+                match gender.as_deref() {
+                    Some("M") => group.male += 1,
+                    Some("F") => group.female += 1,
+                    _ => {}
+                }
+            }
+        }
 
         // Monthly registrations (last 12 months)
-        let monthly_rows = sqlx::query(
+        let monthly_rows = sqlx::query(&format!(
             r#"
             SELECT
-                EXTRACT(YEAR FROM created_at)::INTEGER as year,
-                EXTRACT(MONTH FROM created_at)::INTEGER as month,
-                COUNT(*)::BIGINT as count
-            FROM patients
-            WHERE created_at >= NOW() - INTERVAL '12 months'
+                EXTRACT(YEAR FROM registration_date)::INTEGER as year,
+                EXTRACT(MONTH FROM registration_date)::INTEGER as month,
+                SUM(count)::BIGINT as count
+            FROM {}
+            WHERE registration_date >= (NOW() - INTERVAL '12 months')::DATE
             GROUP BY year, month
             ORDER BY year, month
             "#,
-        )
+            source
+        ))
         .fetch_all(&mut *tx)
         .await?;
 
@@ -537,6 +835,8 @@ impl ReportService {
             by_gender,
             age_distribution,
             monthly_registrations,
+            data_as_of,
+            comparison: None,
         })
     }
 
@@ -545,14 +845,48 @@ impl ReportService {
     /// Generate diagnosis trends report
     ///
     /// Analyzes diagnosis frequency and trends over time.
+    ///
+    /// With `compare_to` and a date range, the summary metrics are compared
+    /// with the same metrics of that period.
     pub async fn get_diagnosis_trends(
         &self,
         filter: DiagnosisReportFilter,
         user_id: Uuid,
+    ) -> Result<DiagnosisTrendsReport> {
+        let compare_to = filter.compare_to;
+        let mut report = self.diagnosis_trends(filter.clone(), user_id).await?;
+
+        let range = report.date_range.clone();
+        if let (Some(compare_to), Some(range)) = (compare_to, range) {
+            let comparison_range = compare_to.range_for(&range);
+            let previous_filter = DiagnosisReportFilter {
+                start_date: Some(comparison_range.start_date),
+                end_date: Some(comparison_range.end_date),
+                compare_to: None,
+                ..filter
+            };
+            let previous = self.diagnosis_trends(previous_filter, user_id).await?;
+            report.comparison = Some(PeriodComparison::new(
+                compare_to,
+                comparison_range,
+                &report.summary_metrics(),
+                &previous.summary_metrics(),
+            ));
+        }
+
+        Ok(report)
+    }
+
+    /// Compute the diagnosis trends report of one period
+    async fn diagnosis_trends(
+        &self,
+        filter: DiagnosisReportFilter,
+        user_id: Uuid,
     ) -> Result<DiagnosisTrendsReport> {
         // Start transaction for RLS context
-        let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-        Self::set_rls_context(&mut tx, user_id).await?;
+        let mut tx = begin_with_rls(&self.pool, user_id)
+            .await
+            .context("Failed to begin transaction")?;
 
         // Determine date range
         let date_range = match (filter.start_date, filter.end_date) {
@@ -566,18 +900,32 @@ impl ReportService {
         // Clamp page size to prevent uncontrolled allocation (CWE-770)
         let limit = filter.limit.unwrap_or(20).min(100) as i64;
 
-        // Total and unique diagnoses — fully parameterized, no dynamic SQL
-        let count_row = sqlx::query(
+        // Read the rollup from the view when fresh; only this constant
+        // source is interpolated, filter values are bound
+        let data_as_of = Self::view_data_as_of(
+            &mut tx,
+            DIAGNOSIS_STATS_VIEW,
+            filter.live.unwrap_or(false),
+        )
+        .await?;
+        let source = if data_as_of.is_some() {
+            DIAGNOSIS_STATS_VIEW
+        } else {
+            LIVE_DIAGNOSIS_STATS
+        };
+
+        // Total and unique diagnoses
+        let count_row = sqlx::query(&format!(
             r#"
             SELECT
-                COUNT(*)::BIGINT as total,
-                COUNT(DISTINCT vd.icd10_code)::BIGINT as unique_codes
-            FROM visit_diagnoses vd
-            JOIN visits v ON vd.visit_id = v.id
-            WHERE ($1::DATE IS NULL OR v.visit_date >= $1)
-              AND ($2::DATE IS NULL OR v.visit_date <= $2)
+                COALESCE(SUM(count), 0)::BIGINT as total,
+                COUNT(DISTINCT icd10_code)::BIGINT as unique_codes
+            FROM {}
+            WHERE ($1::DATE IS NULL OR visit_date >= $1)
+              AND ($2::DATE IS NULL OR visit_date <= $2)
             "#,
-        )
+            source
+        ))
         .bind(filter.start_date)
         .bind(filter.end_date)
         .fetch_one(&mut *tx)
@@ -587,21 +935,21 @@ impl ReportService {
         let unique_codes: i64 = count_row.try_get("unique_codes").unwrap_or(0);
 
         // Top diagnoses
-        let top_rows = sqlx::query(
+        let top_rows = sqlx::query(&format!(
             r#"
             SELECT
-                vd.icd10_code,
-                vd.icd10_description as description,
-                COUNT(*)::BIGINT as count
-            FROM visit_diagnoses vd
-            JOIN visits v ON vd.visit_id = v.id
-            WHERE ($1::DATE IS NULL OR v.visit_date >= $1)
-              AND ($2::DATE IS NULL OR v.visit_date <= $2)
-            GROUP BY vd.icd10_code, vd.icd10_description
+                icd10_code,
+                icd10_description as description,
+                SUM(count)::BIGINT as count
+            FROM {}
+            WHERE ($1::DATE IS NULL OR visit_date >= $1)
+              AND ($2::DATE IS NULL OR visit_date <= $2)
+            GROUP BY icd10_code, icd10_description
             ORDER BY count DESC
             LIMIT $3
             "#,
-        )
+            source
+        ))
         .bind(filter.start_date)
         .bind(filter.end_date)
         .bind(limit)
@@ -627,19 +975,21 @@ impl ReportService {
         }
 
         // Monthly trend (last 12 months)
-        let monthly_query = r#"
+        let monthly_query = format!(
+            r#"
             SELECT
-                EXTRACT(YEAR FROM v.visit_date)::INTEGER as year,
-                EXTRACT(MONTH FROM v.visit_date)::INTEGER as month,
-                COUNT(vd.id)::BIGINT as count
-            FROM visit_diagnoses vd
-            JOIN visits v ON vd.visit_id = v.id
-            WHERE v.visit_date >= NOW() - INTERVAL '12 months'
+                EXTRACT(YEAR FROM visit_date)::INTEGER as year,
+                EXTRACT(MONTH FROM visit_date)::INTEGER as month,
+                SUM(count)::BIGINT as count
+            FROM {}
+            WHERE visit_date >= NOW() - INTERVAL '12 months'
             GROUP BY year, month
             ORDER BY year, month
-        "#;
+            "#,
+            source
+        );
 
-        let monthly_rows = sqlx::query(monthly_query).fetch_all(&mut *tx).await?;
+        let monthly_rows = sqlx::query(&monthly_query).fetch_all(&mut *tx).await?;
 
         let mut monthly_trend = Vec::new();
         for row in monthly_rows {
@@ -655,19 +1005,19 @@ impl ReportService {
         }
 
         // Diagnoses by category (ICD-10 chapter)
-        let category_rows = sqlx::query(
+        let category_rows = sqlx::query(&format!(
             r#"
             SELECT
-                UPPER(SUBSTRING(vd.icd10_code, 1, 1)) as category,
-                COUNT(*)::BIGINT as count
-            FROM visit_diagnoses vd
-            JOIN visits v ON vd.visit_id = v.id
-            WHERE ($1::DATE IS NULL OR v.visit_date >= $1)
-              AND ($2::DATE IS NULL OR v.visit_date <= $2)
+                UPPER(SUBSTRING(icd10_code, 1, 1)) as category,
+                SUM(count)::BIGINT as count
+            FROM {}
+            WHERE ($1::DATE IS NULL OR visit_date >= $1)
+              AND ($2::DATE IS NULL OR visit_date <= $2)
             GROUP BY category
             ORDER BY count DESC
             "#,
-        )
+            source
+        ))
         .bind(filter.start_date)
         .bind(filter.end_date)
         .fetch_all(&mut *tx)
@@ -693,6 +1043,8 @@ impl ReportService {
             top_diagnoses,
             monthly_trend,
             by_category,
+            data_as_of,
+            comparison: None,
         })
     }
 
@@ -701,14 +1053,48 @@ impl ReportService {
     /// Generate provider productivity report
     ///
     /// Analyzes provider workload and efficiency metrics.
+    ///
+    /// With `compare_to` and a date range, the summary metrics are compared
+    /// with the same metrics of that period.
     pub async fn get_provider_productivity(
         &self,
         filter: ProductivityReportFilter,
         user_id: Uuid,
+    ) -> Result<ProviderProductivityReport> {
+        let compare_to = filter.compare_to;
+        let mut report = self.provider_productivity(filter.clone(), user_id).await?;
+
+        let range = report.date_range.clone();
+        if let (Some(compare_to), Some(range)) = (compare_to, range) {
+            let comparison_range = compare_to.range_for(&range);
+            let previous_filter = ProductivityReportFilter {
+                start_date: Some(comparison_range.start_date),
+                end_date: Some(comparison_range.end_date),
+                compare_to: None,
+                ..filter
+            };
+            let previous = self.provider_productivity(previous_filter, user_id).await?;
+            report.comparison = Some(PeriodComparison::new(
+                compare_to,
+                comparison_range,
+                &report.summary_metrics(),
+                &previous.summary_metrics(),
+            ));
+        }
+
+        Ok(report)
+    }
+
+    /// Compute the provider productivity report of one period
+    async fn provider_productivity(
+        &self,
+        filter: ProductivityReportFilter,
+        user_id: Uuid,
     ) -> Result<ProviderProductivityReport> {
         // Start transaction for RLS context
-        let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-        Self::set_rls_context(&mut tx, user_id).await?;
+        let mut tx = begin_with_rls(&self.pool, user_id)
+            .await
+            .context("Failed to begin transaction")?;
 
         // Determine if we have a date range (None means "All Time")
         let date_range = match (filter.start_date, filter.end_date) {
@@ -779,6 +1165,33 @@ impl ReportService {
         .await
         .unwrap_or(0.0);
 
+        // Waiting room: minutes from arrival (checked_in_at) to the start of
+        // the visit, for the appointments that went through the waiting room
+        let wait_row = sqlx::query(
+            r#"
+            SELECT
+                COALESCE(AVG(wait_minutes), 0)::FLOAT as avg_wait,
+                COALESCE(PERCENTILE_CONT(0.5) WITHIN GROUP (ORDER BY wait_minutes), 0)::FLOAT as median_wait
+            FROM (
+                SELECT EXTRACT(EPOCH FROM (visit_started_at - checked_in_at)) / 60 as wait_minutes
+                FROM appointments
+                WHERE checked_in_at IS NOT NULL
+                  AND visit_started_at >= checked_in_at
+                  AND ($1::DATE IS NULL OR scheduled_start::DATE >= $1)
+                  AND ($2::DATE IS NULL OR scheduled_start::DATE <= $2)
+                  AND ($3::UUID IS NULL OR provider_id = $3)
+            ) waits
+            "#,
+        )
+        .bind(start_date)
+        .bind(end_date)
+        .bind(filter.provider_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let avg_wait_minutes: f64 = wait_row.try_get("avg_wait").unwrap_or(0.0);
+        let median_wait_minutes: f64 = wait_row.try_get("median_wait").unwrap_or(0.0);
+
         let summary = ProductivitySummary {
             total_appointments,
             completed_appointments,
@@ -786,6 +1199,8 @@ impl ReportService {
             total_prescriptions,
             total_documents,
             avg_appointment_duration: avg_duration,
+            avg_wait_minutes,
+            median_wait_minutes,
         };
 
         // Per-provider breakdown - handle with/without date range
@@ -799,7 +1214,8 @@ impl ReportService {
                     COALESCE((SELECT COUNT(*) FROM visits v WHERE v.provider_id = u.id AND v.visit_date >= $1 AND v.visit_date <= $2), 0)::BIGINT as visits_documented,
                     COALESCE((SELECT COUNT(*) FROM prescriptions p WHERE p.provider_id = u.id AND p.prescribed_date >= $1 AND p.prescribed_date <= $2), 0)::BIGINT as prescriptions_written,
                     COALESCE((SELECT COUNT(*) FROM generated_documents d WHERE d.created_by = u.id AND d.created_at::DATE >= $1 AND d.created_at::DATE <= $2 AND d.status != 'DELETED'), 0)::BIGINT as documents_generated,
-                    COALESCE((SELECT COUNT(DISTINCT patient_id) FROM visits v WHERE v.provider_id = u.id AND v.visit_date >= $1 AND v.visit_date <= $2), 0)::BIGINT as unique_patients_seen
+                    COALESCE((SELECT COUNT(DISTINCT patient_id) FROM visits v WHERE v.provider_id = u.id AND v.visit_date >= $1 AND v.visit_date <= $2), 0)::BIGINT as unique_patients_seen,
+                    COALESCE((SELECT AVG(EXTRACT(EPOCH FROM (a.visit_started_at - a.checked_in_at)) / 60) FROM appointments a WHERE a.provider_id = u.id AND a.scheduled_start::DATE >= $1 AND a.scheduled_start::DATE <= $2 AND a.checked_in_at IS NOT NULL AND a.visit_started_at >= a.checked_in_at), 0)::FLOAT as avg_wait_minutes
                 FROM users u
                 WHERE u.role IN ('ADMIN', 'DOCTOR')
                 ORDER BY visits_documented DESC
@@ -824,7 +1240,8 @@ impl ReportService {
                     COALESCE((SELECT COUNT(*) FROM visits v WHERE v.provider_id = u.id), 0)::BIGINT as visits_documented,
                     COALESCE((SELECT COUNT(*) FROM prescriptions p WHERE p.provider_id = u.id), 0)::BIGINT as prescriptions_written,
                     COALESCE((SELECT COUNT(*) FROM generated_documents d WHERE d.created_by = u.id AND d.status != 'DELETED'), 0)::BIGINT as documents_generated,
-                    COALESCE((SELECT COUNT(DISTINCT patient_id) FROM visits v WHERE v.provider_id = u.id), 0)::BIGINT as unique_patients_seen
+                    COALESCE((SELECT COUNT(DISTINCT patient_id) FROM visits v WHERE v.provider_id = u.id), 0)::BIGINT as unique_patients_seen,
+                    COALESCE((SELECT AVG(EXTRACT(EPOCH FROM (a.visit_started_at - a.checked_in_at)) / 60) FROM appointments a WHERE a.provider_id = u.id AND a.checked_in_at IS NOT NULL AND a.visit_started_at >= a.checked_in_at), 0)::FLOAT as avg_wait_minutes
                 FROM users u
                 WHERE u.role IN ('ADMIN', 'DOCTOR')
                 ORDER BY visits_documented DESC
@@ -860,6 +1277,7 @@ impl ReportService {
             let prescriptions_written: i64 = row.try_get("prescriptions_written").unwrap_or(0);
             let documents_generated: i64 = row.try_get("documents_generated").unwrap_or(0);
             let unique_patients_seen: i64 = row.try_get("unique_patients_seen").unwrap_or(0);
+            let avg_wait_minutes: f64 = row.try_get("avg_wait_minutes").unwrap_or(0.0);
 
             let avg_visits_per_day = visits_documented as f64 / days_in_range;
             let completion_rate = if appointments_completed > 0 {
@@ -886,6 +1304,7 @@ impl ReportService {
                 unique_patients_seen,
                 avg_visits_per_day,
                 completion_rate,
+                avg_wait_minutes,
             });
         }
 
@@ -901,6 +1320,7 @@ impl ReportService {
             date_range: report_date_range,
             summary,
             by_provider,
+            comparison: None,
         })
     }
 
@@ -910,14 +1330,48 @@ impl ReportService {
     ///
     /// Note: Actual revenue tracking requires billing module integration.
     /// This provides visit-based metrics as a proxy for revenue analysis.
+    ///
+    /// With `compare_to` and a date range, the summary metrics are compared
+    /// with the same metrics of that period.
     pub async fn get_revenue_report(
         &self,
         filter: RevenueReportFilter,
         user_id: Uuid,
+    ) -> Result<RevenueReport> {
+        let compare_to = filter.compare_to;
+        let mut report = self.revenue_report(filter.clone(), user_id).await?;
+
+        let range = report.date_range.clone();
+        if let (Some(compare_to), Some(range)) = (compare_to, range) {
+            let comparison_range = compare_to.range_for(&range);
+            let previous_filter = RevenueReportFilter {
+                start_date: Some(comparison_range.start_date),
+                end_date: Some(comparison_range.end_date),
+                compare_to: None,
+                ..filter
+            };
+            let previous = self.revenue_report(previous_filter, user_id).await?;
+            report.comparison = Some(PeriodComparison::new(
+                compare_to,
+                comparison_range,
+                &report.summary_metrics(),
+                &previous.summary_metrics(),
+            ));
+        }
+
+        Ok(report)
+    }
+
+    /// Compute the revenue report of one period
+    async fn revenue_report(
+        &self,
+        filter: RevenueReportFilter,
+        user_id: Uuid,
     ) -> Result<RevenueReport> {
         // Start transaction for RLS context
-        let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-        Self::set_rls_context(&mut tx, user_id).await?;
+        let mut tx = begin_with_rls(&self.pool, user_id)
+            .await
+            .context("Failed to begin transaction")?;
 
         // Determine date range
         let (start_date, end_date) = match (filter.start_date, filter.end_date) {
@@ -983,6 +1437,7 @@ impl ReportService {
             visits_by_type,
             avg_visits_per_day,
             note: "Revenue tracking requires billing module integration. These metrics are based on visit counts.".to_string(),
+            comparison: None,
         })
     }
 
@@ -993,8 +1448,9 @@ impl ReportService {
     /// Provides quick stats and recent activity for the main dashboard.
     pub async fn get_dashboard_report(&self, user_id: Uuid) -> Result<DashboardReport> {
         // Start transaction for RLS context
-        let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-        Self::set_rls_context(&mut tx, user_id).await?;
+        let mut tx = begin_with_rls(&self.pool, user_id)
+            .await
+            .context("Failed to begin transaction")?;
 
         let today = Utc::now().date_naive();
         let week_start = today - chrono::Duration::days(today.weekday().num_days_from_monday() as i64);
@@ -1155,14 +1611,195 @@ impl ReportService {
             new_patients,
         };
 
+        // Post-visit surveys sent in the period
+        let (surveys_sent, responses, average_score, promoters, passives, detractors): (
+            i64,
+            i64,
+            Option<f64>,
+            i64,
+            i64,
+            i64,
+        ) = sqlx::query_as(
+            r#"
+            SELECT
+                COUNT(*)::BIGINT,
+                COUNT(responded_at)::BIGINT,
+                AVG(score)::FLOAT8,
+                COUNT(*) FILTER (WHERE score >= 9)::BIGINT,
+                COUNT(*) FILTER (WHERE score BETWEEN 7 AND 8)::BIGINT,
+                COUNT(*) FILTER (WHERE score <= 6)::BIGINT
+            FROM visit_surveys
+            WHERE sent_at::DATE > $1
+            "#,
+        )
+        .bind(today - chrono::Duration::days(DASHBOARD_SATISFACTION_DAYS))
+        .fetch_one(&mut *tx)
+        .await
+        .unwrap_or((0, 0, None, 0, 0, 0));
+
+        let satisfaction = SatisfactionSummary {
+            period_days: DASHBOARD_SATISFACTION_DAYS,
+            surveys_sent,
+            responses,
+            response_rate: if surveys_sent > 0 {
+                (responses as f64 / surveys_sent as f64) * 100.0
+            } else {
+                0.0
+            },
+            average_score,
+            promoters,
+            passives,
+            detractors,
+            nps: net_promoter_score(promoters, passives, detractors),
+        };
+
         tx.commit().await.context("Failed to commit transaction")?;
 
         Ok(DashboardReport {
             generated_at: Utc::now(),
             quick_stats,
             recent_activity,
+            satisfaction,
         })
     }
+
+    /// Load de-identified records for a research dataset
+    ///
+    /// Only patients with a visit in the period are included; anonymized
+    /// patients are excluded. Dates of birth and addresses are decrypted in
+    /// memory to derive the age (at the end of the period for patients, at
+    /// the visit for diagnoses) and the province; nothing else leaves the
+    /// patient row. Returns the records and the number of patients whose data
+    /// could not be decrypted.
+    pub async fn get_research_records(
+        &self,
+        dataset: ResearchDatasetType,
+        period: &ReportDateRange,
+        profile: &AnonymizationProfile,
+        encryption_key: &EncryptionKey,
+        user_id: Uuid,
+    ) -> Result<(Vec<ResearchRecord>, usize)> {
+        let mut tx = begin_with_rls(&self.pool, user_id)
+            .await
+            .context("Failed to begin transaction")?;
+
+        let patients: Vec<(Uuid, String, String, Option<serde_json::Value>)> = sqlx::query_as(
+            r#"
+            SELECT p.id, p.date_of_birth, p.gender, p.address
+            FROM patients p
+            WHERE p.anonymized_at IS NULL
+              AND EXISTS (
+                  SELECT 1 FROM visits v
+                  WHERE v.patient_id = p.id AND v.visit_date BETWEEN $1 AND $2
+              )
+            "#,
+        )
+        .bind(period.start_date)
+        .bind(period.end_date)
+        .fetch_all(&mut *tx)
+        .await
+        .context("Failed to fetch patients")?;
+
+        // Per patient: date of birth, gender, province
+        let mut demographics: HashMap<Uuid, (NaiveDate, String, Option<String>)> = HashMap::new();
+        let mut unreadable = 0usize;
+        for (patient_id, encrypted_dob, gender, address) in patients {
+            let dob = encryption_key
+                .decrypt(&encrypted_dob)
+                .ok()
+                .and_then(|dob| NaiveDate::parse_from_str(dob.trim(), "%Y-%m-%d").ok());
+            let Some(dob) = dob else {
+                unreadable += 1;
+                continue;
+            };
+            let region = address
+                .as_ref()
+                .and_then(|json| json.as_str())
+                .and_then(|encrypted| encryption_key.decrypt_json::<Address>(encrypted).ok())
+                .map(|address| address.state);
+            demographics.insert(patient_id, (dob, gender, region));
+        }
+
+        let diagnoses: Vec<(Uuid, NaiveDate, String, Option<String>)> = sqlx::query_as(
+            r#"
+            SELECT v.patient_id, v.visit_date, vd.icd10_code, vd.diagnosis_type
+            FROM visit_diagnoses vd
+            JOIN visits v ON vd.visit_id = v.id
+            WHERE v.visit_date BETWEEN $1 AND $2
+            "#,
+        )
+        .bind(period.start_date)
+        .bind(period.end_date)
+        .fetch_all(&mut *tx)
+        .await
+        .context("Failed to fetch diagnoses")?;
+
+        let records = match dataset {
+            ResearchDatasetType::Diagnoses => diagnoses
+                .into_iter()
+                .filter_map(|(patient_id, visit_date, code, diagnosis_type)| {
+                    let (dob, gender, region) = demographics.get(&patient_id)?;
+                    Some(ResearchRecord {
+                        subject: patient_id,
+                        age: visit_date.years_since(*dob),
+                        gender: gender.clone(),
+                        region: region.clone(),
+                        event_date: Some(visit_date),
+                        values: vec![
+                            profile.icd10(&code),
+                            diagnosis_type.unwrap_or_default(),
+                        ],
+                    })
+                })
+                .collect(),
+            ResearchDatasetType::Patients => {
+                let visit_counts: Vec<(Uuid, i64)> = sqlx::query_as(
+                    r#"
+                    SELECT patient_id, COUNT(*)::BIGINT
+                    FROM visits
+                    WHERE visit_date BETWEEN $1 AND $2
+                    GROUP BY patient_id
+                    "#,
+                )
+                .bind(period.start_date)
+                .bind(period.end_date)
+                .fetch_all(&mut *tx)
+                .await
+                .context("Failed to count visits")?;
+
+                let mut groups: HashMap<Uuid, std::collections::BTreeSet<String>> = HashMap::new();
+                for (patient_id, _, code, _) in &diagnoses {
+                    groups
+                        .entry(*patient_id)
+                        .or_default()
+                        .insert(profile.icd10(code));
+                }
+
+                visit_counts
+                    .into_iter()
+                    .filter_map(|(patient_id, visits)| {
+                        let (dob, gender, region) = demographics.get(&patient_id)?;
+                        let diagnosis_groups = groups
+                            .remove(&patient_id)
+                            .map(|codes| codes.into_iter().collect::<Vec<_>>().join(";"))
+                            .unwrap_or_default();
+                        Some(ResearchRecord {
+                            subject: patient_id,
+                            age: period.end_date.years_since(*dob),
+                            gender: gender.clone(),
+                            region: region.clone(),
+                            event_date: None,
+                            values: vec![visits.to_string(), diagnosis_groups],
+                        })
+                    })
+                    .collect()
+            }
+        };
+
+        tx.commit().await.context("Failed to commit transaction")?;
+
+        Ok((records, unreadable))
+    }
 }
 
 #[cfg(test)]
@@ -1189,6 +1826,44 @@ mod tests {
         assert_eq!(ReportService::day_of_week_name(100), "Unknown");
     }
 
+    // ========== HEATMAP TESTS ==========
+
+    #[test]
+    fn test_weekday_occurrences() {
+        // 2026-03-01 is a Sunday; two full weeks plus Sunday-Tuesday
+        let start = NaiveDate::from_ymd_opt(2026, 3, 1).unwrap();
+        let end = NaiveDate::from_ymd_opt(2026, 3, 17).unwrap();
+        assert_eq!(ReportService::weekday_occurrences(start, end), [3, 3, 3, 2, 2, 2, 2]);
+
+        assert_eq!(ReportService::weekday_occurrences(start, start), [1, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(ReportService::weekday_occurrences(end, start), [0; 7]);
+    }
+
+    #[test]
+    fn test_build_heatmap() {
+        let start = NaiveDate::from_ymd_opt(2026, 3, 1).unwrap();
+        let end = NaiveDate::from_ymd_opt(2026, 3, 14).unwrap(); // two full weeks
+        let slots = vec![
+            HeatmapSlotCount { day: 1, hour: 9, bookings: 6, no_shows: 1 },
+            HeatmapSlotCount { day: 3, hour: 15, bookings: 2, no_shows: 0 },
+            HeatmapSlotCount { day: 9, hour: 9, bookings: 100, no_shows: 0 }, // ignored
+        ];
+
+        let heatmap = ReportService::build_heatmap(&slots, start, end);
+
+        assert_eq!(heatmap.days.len(), 7);
+        assert_eq!(heatmap.days[1], "Monday");
+        assert!(heatmap.bookings.iter().all(|row| row.len() == 24));
+        assert_eq!(heatmap.bookings[1][9], 6);
+        assert_eq!(heatmap.no_shows[1][9], 1);
+        assert_eq!(heatmap.booking_density[1][9], 3.0);
+        assert_eq!(heatmap.no_show_density[1][9], 0.5);
+        assert_eq!(heatmap.booking_density[0][9], 0.0);
+        assert_eq!(heatmap.max_bookings, 6);
+        assert_eq!(heatmap.total_bookings, 8);
+        assert_eq!(heatmap.total_no_shows, 1);
+    }
+
     // ========== MONTH NAME TESTS ==========
 
     #[test]
@@ -1214,6 +1889,19 @@ mod tests {
         assert_eq!(ReportService::month_name(-1), "Unknown");
     }
 
+    // ========== AGE GROUP TESTS ==========
+
+    #[test]
+    fn test_age_group_index_boundaries() {
+        assert_eq!(AGE_GROUPS[ReportService::age_group_index(0)].0, "0-18");
+        assert_eq!(AGE_GROUPS[ReportService::age_group_index(18)].0, "0-18");
+        assert_eq!(AGE_GROUPS[ReportService::age_group_index(19)].0, "19-30");
+        assert_eq!(AGE_GROUPS[ReportService::age_group_index(65)].0, "51-65");
+        assert_eq!(AGE_GROUPS[ReportService::age_group_index(80)].0, "66-80");
+        assert_eq!(AGE_GROUPS[ReportService::age_group_index(81)].0, "80+");
+        assert_eq!(AGE_GROUPS[ReportService::age_group_index(120)].0, "80+");
+    }
+
     // ========== ICD-10 CATEGORY TESTS ==========
 
     #[test]
@@ -1428,6 +2116,8 @@ mod tests {
             start_date: None,
             end_date: None,
             provider_id: None,
+            live: None,
+            compare_to: None,
         };
         assert!(filter.start_date.is_none());
         assert!(filter.end_date.is_none());
@@ -1442,6 +2132,8 @@ mod tests {
             start_date: Some(start),
             end_date: Some(end),
             provider_id: None,
+            live: None,
+            compare_to: None,
         };
         assert_eq!(filter.start_date, Some(start));
         assert_eq!(filter.end_date, Some(end));
@@ -1452,6 +2144,8 @@ mod tests {
         let filter = PatientReportFilter {
             start_date: None,
             end_date: None,
+            live: None,
+            compare_to: None,
         };
         assert!(filter.start_date.is_none());
         assert!(filter.end_date.is_none());
@@ -1464,6 +2158,8 @@ mod tests {
             end_date: None,
             provider_id: None,
             limit: None,
+            live: None,
+            compare_to: None,
         };
         assert!(filter.start_date.is_none());
         assert!(filter.end_date.is_none());
@@ -1478,6 +2174,8 @@ mod tests {
             end_date: None,
             provider_id: None,
             limit: Some(10),
+            live: None,
+            compare_to: None,
         };
         assert_eq!(filter.limit, Some(10));
     }
@@ -1598,6 +2296,8 @@ mod tests {
             total_prescriptions: 120,
             total_documents: 50,
             avg_appointment_duration: 25.5,
+            avg_wait_minutes: 12.0,
+            median_wait_minutes: 10.0,
         };
         assert_eq!(summary.total_appointments, 100);
         assert_eq!(summary.completed_appointments, 85);
@@ -1605,6 +2305,7 @@ mod tests {
         assert_eq!(summary.total_prescriptions, 120);
         assert_eq!(summary.total_documents, 50);
         assert!((summary.avg_appointment_duration - 25.5).abs() < 0.001);
+        assert!((summary.avg_wait_minutes - 12.0).abs() < 0.001);
     }
 
     #[test]
@@ -0,0 +1,468 @@
+/*!
+ * Report Subscription Service
+ *
+ * Manages the report subscriptions in `report_subscriptions` and delivers
+ * them: the report of the last complete period is generated with
+ * ReportService/ReportExportService, as the subscription owner, and emailed
+ * to every recipient as an attachment.
+ *
+ * A background job checks for due subscriptions every few minutes. Due rows
+ * are claimed (next delivery rescheduled) in a FOR UPDATE SKIP LOCKED
+ * transaction before anything is sent, so with several replicas each
+ * delivery goes out once. The outcome of the last delivery is kept on the
+ * subscription and written to the audit log.
+ */
+
+use crate::models::{
+    AuditAction, AuditLog, CreateAuditLog, CreateReportSubscriptionRequest, EntityType,
+    ExportReportRequest, ReportDateRange, ReportDeliveryStatus, ReportSubscription,
+    ReportSubscriptionResponse, UpdateReportSubscriptionRequest,
+};
+use crate::services::{EmailService, ReportExportService, ReportService};
+use crate::utils::{AppError, EncryptionKey, Result};
+use chrono::Utc;
+use sqlx::PgPool;
+use tokio::time::{sleep, Duration as TokioDuration};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// Seconds between checks for due subscriptions
+const CHECK_INTERVAL_SECS: u64 = 300;
+
+/// Subscriptions claimed per check
+const CLAIM_BATCH_SIZE: i64 = 20;
+
+const SUBSCRIPTION_COLUMNS: &str = r#"
+    id, name, report_type, filters, format, cadence, recipients, is_active,
+    next_run_at, last_run_at, last_status, last_error, created_by, created_at, updated_at
+"#;
+
+/// Report subscription service
+pub struct ReportSubscriptionService {
+    pool: PgPool,
+}
+
+impl ReportSubscriptionService {
+    /// Create a new report subscription service
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    // ==================== Subscriptions ====================
+
+    /// List subscriptions: every one for administrators, otherwise the
+    /// caller's own
+    pub async fn list(
+        &self,
+        user_id: Uuid,
+        is_admin: bool,
+    ) -> Result<Vec<ReportSubscriptionResponse>> {
+        let rows = sqlx::query_as::<_, ReportSubscription>(&format!(
+            r#"
+            SELECT {} FROM report_subscriptions
+            WHERE $1 OR created_by = $2
+            ORDER BY name, created_at
+            "#,
+            SUBSCRIPTION_COLUMNS
+        ))
+        .bind(is_admin)
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(to_response).collect()
+    }
+
+    /// Get a subscription the caller may see
+    pub async fn get(
+        &self,
+        subscription_id: Uuid,
+        user_id: Uuid,
+        is_admin: bool,
+    ) -> Result<ReportSubscriptionResponse> {
+        to_response(self.get_row(subscription_id, user_id, is_admin).await?)
+    }
+
+    /// Create a subscription owned by `created_by`; the first delivery is
+    /// at the start of the next period
+    pub async fn create(
+        &self,
+        req: &CreateReportSubscriptionRequest,
+        created_by: Uuid,
+    ) -> Result<ReportSubscriptionResponse> {
+        let row = sqlx::query_as::<_, ReportSubscription>(&format!(
+            r#"
+            INSERT INTO report_subscriptions (
+                name, report_type, filters, format, cadence, recipients, next_run_at, created_by
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING {}
+            "#,
+            SUBSCRIPTION_COLUMNS
+        ))
+        .bind(req.name.trim())
+        .bind(req.report_type.as_str())
+        .bind(serde_json::to_value(&req.filters).unwrap_or_default())
+        .bind(req.format.as_str())
+        .bind(req.cadence.as_str())
+        .bind(&req.recipients)
+        .bind(req.cadence.next_run_after(Utc::now()))
+        .bind(created_by)
+        .fetch_one(&self.pool)
+        .await?;
+
+        to_response(row)
+    }
+
+    /// Update a subscription the caller may see
+    pub async fn update(
+        &self,
+        subscription_id: Uuid,
+        req: &UpdateReportSubscriptionRequest,
+        user_id: Uuid,
+        is_admin: bool,
+    ) -> Result<ReportSubscriptionResponse> {
+        let current = to_response(self.get_row(subscription_id, user_id, is_admin).await?)?;
+
+        let filters = req
+            .filters
+            .as_ref()
+            .map(|filters| serde_json::to_value(filters).unwrap_or_default());
+        // A new cadence or a reactivation starts over from the next period,
+        // so a paused subscription does not catch up on missed deliveries
+        let reactivated = req.is_active == Some(true) && !current.is_active;
+        let next_run_at = (req.cadence.is_some() || reactivated).then(|| {
+            req.cadence
+                .unwrap_or(current.cadence)
+                .next_run_after(Utc::now())
+        });
+
+        let row = sqlx::query_as::<_, ReportSubscription>(&format!(
+            r#"
+            UPDATE report_subscriptions
+            SET name = COALESCE($2, name),
+                filters = COALESCE($3, filters),
+                format = COALESCE($4, format),
+                cadence = COALESCE($5, cadence),
+                next_run_at = COALESCE($6, next_run_at),
+                recipients = COALESCE($7, recipients),
+                is_active = COALESCE($8, is_active),
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING {}
+            "#,
+            SUBSCRIPTION_COLUMNS
+        ))
+        .bind(subscription_id)
+        .bind(req.name.as_deref().map(str::trim))
+        .bind(filters)
+        .bind(req.format.map(|format| format.as_str()))
+        .bind(req.cadence.map(|cadence| cadence.as_str()))
+        .bind(next_run_at)
+        .bind(req.recipients.as_ref())
+        .bind(req.is_active)
+        .fetch_one(&self.pool)
+        .await?;
+
+        to_response(row)
+    }
+
+    /// Delete a subscription the caller may see
+    pub async fn delete(&self, subscription_id: Uuid, user_id: Uuid, is_admin: bool) -> Result<()> {
+        self.get_row(subscription_id, user_id, is_admin).await?;
+
+        sqlx::query("DELETE FROM report_subscriptions WHERE id = $1")
+            .bind(subscription_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_row(
+        &self,
+        subscription_id: Uuid,
+        user_id: Uuid,
+        is_admin: bool,
+    ) -> Result<ReportSubscription> {
+        sqlx::query_as::<_, ReportSubscription>(&format!(
+            "SELECT {} FROM report_subscriptions WHERE id = $1 AND ($2 OR created_by = $3)",
+            SUBSCRIPTION_COLUMNS
+        ))
+        .bind(subscription_id)
+        .bind(is_admin)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| {
+            AppError::NotFound(format!("Report subscription {} not found", subscription_id))
+        })
+    }
+
+    // ==================== Delivery ====================
+
+    /// Deliver a subscription now, for the last complete period, without
+    /// changing its schedule
+    pub async fn send_now(
+        &self,
+        subscription_id: Uuid,
+        user_id: Uuid,
+        is_admin: bool,
+        email_service: &EmailService,
+        encryption_key: Option<&EncryptionKey>,
+    ) -> Result<ReportSubscriptionResponse> {
+        let subscription = self.get_row(subscription_id, user_id, is_admin).await?;
+        let cadence = to_response(subscription.clone())?.cadence;
+        let period = cadence.previous_period(Utc::now().date_naive());
+
+        self.deliver(subscription, &period, email_service, encryption_key)
+            .await
+    }
+
+    /// Deliver every due subscription, returning how many were delivered
+    /// (successfully or not) by this instance
+    pub async fn run_due(
+        &self,
+        email_service: &EmailService,
+        encryption_key: Option<&EncryptionKey>,
+    ) -> Result<usize> {
+        let due = self.claim_due().await?;
+        let count = due.len();
+
+        for (subscription, period) in due {
+            let subscription_id = subscription.id;
+            if let Err(e) = self
+                .deliver(subscription, &period, email_service, encryption_key)
+                .await
+            {
+                error!(
+                    "Report subscription {} delivery failed: {}",
+                    subscription_id, e
+                );
+            }
+        }
+
+        Ok(count)
+    }
+
+    /// Claim due subscriptions: their next delivery is rescheduled before
+    /// they are sent, so no other instance sends them too
+    ///
+    /// Each comes with the period its delivery covers, derived from when it
+    /// was due, so a delivery sent late still covers the right period.
+    async fn claim_due(&self) -> Result<Vec<(ReportSubscription, ReportDateRange)>> {
+        let mut tx = self.pool.begin().await?;
+
+        let due = sqlx::query_as::<_, ReportSubscription>(&format!(
+            r#"
+            SELECT {} FROM report_subscriptions
+            WHERE is_active AND next_run_at <= NOW()
+            ORDER BY next_run_at
+            LIMIT $1
+            FOR UPDATE SKIP LOCKED
+            "#,
+            SUBSCRIPTION_COLUMNS
+        ))
+        .bind(CLAIM_BATCH_SIZE)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let now = Utc::now();
+        let mut claimed = Vec::with_capacity(due.len());
+        for subscription in due {
+            let cadence = to_response(subscription.clone())?.cadence;
+            sqlx::query("UPDATE report_subscriptions SET next_run_at = $2 WHERE id = $1")
+                .bind(subscription.id)
+                .bind(cadence.next_run_after(now))
+                .execute(&mut *tx)
+                .await?;
+
+            let period = cadence.previous_period(subscription.next_run_at.date_naive());
+            claimed.push((subscription, period));
+        }
+
+        tx.commit().await?;
+        Ok(claimed)
+    }
+
+    /// Generate the report of `period` and email it to every recipient,
+    /// then record the outcome on the subscription and in the audit log
+    async fn deliver(
+        &self,
+        subscription: ReportSubscription,
+        period: &ReportDateRange,
+        email_service: &EmailService,
+        encryption_key: Option<&EncryptionKey>,
+    ) -> Result<ReportSubscriptionResponse> {
+        let response = to_response(subscription)?;
+
+        let (status, error_message) = match self
+            .send_report(&response, period, email_service, encryption_key)
+            .await
+        {
+            Ok(failures) if failures.is_empty() => (ReportDeliveryStatus::Sent, None),
+            Ok(failures) if failures.len() < response.recipients.len() => (
+                ReportDeliveryStatus::PartiallySent,
+                Some(failures.join("; ")),
+            ),
+            Ok(failures) => (ReportDeliveryStatus::Failed, Some(failures.join("; "))),
+            Err(e) => (ReportDeliveryStatus::Failed, Some(e)),
+        };
+
+        if let Some(ref message) = error_message {
+            warn!(
+                "Report subscription {} delivery {}: {}",
+                response.id,
+                status.as_str(),
+                message
+            );
+        }
+
+        let row = sqlx::query_as::<_, ReportSubscription>(&format!(
+            r#"
+            UPDATE report_subscriptions
+            SET last_run_at = NOW(), last_status = $2, last_error = $3
+            WHERE id = $1
+            RETURNING {}
+            "#,
+            SUBSCRIPTION_COLUMNS
+        ))
+        .bind(response.id)
+        .bind(status.as_str())
+        .bind(&error_message)
+        .fetch_one(&self.pool)
+        .await?;
+
+        let _ = AuditLog::create(
+            &self.pool,
+            CreateAuditLog {
+                user_id: Some(response.created_by),
+                action: AuditAction::Export,
+                entity_type: EntityType::ReportSubscription,
+                entity_id: Some(response.id.to_string()),
+                changes: Some(serde_json::json!({
+                    "report_type": response.report_type,
+                    "format": response.format,
+                    "start_date": period.start_date,
+                    "end_date": period.end_date,
+                    "recipients": response.recipients.len(),
+                    "status": status,
+                    "error_message": error_message,
+                })),
+                ip_address: None,
+                user_agent: None,
+                request_id: None,
+            },
+        )
+        .await;
+
+        to_response(row)
+    }
+
+    /// Email the report to every recipient, returning the failures
+    ///
+    /// Err when the report cannot be generated (nothing is sent).
+    async fn send_report(
+        &self,
+        subscription: &ReportSubscriptionResponse,
+        period: &ReportDateRange,
+        email_service: &EmailService,
+        encryption_key: Option<&EncryptionKey>,
+    ) -> std::result::Result<Vec<String>, String> {
+        // Reports are generated with the owner's permissions, which must
+        // still allow reading reports
+        let owner: Option<(String, bool)> =
+            sqlx::query_as("SELECT role::TEXT, is_active FROM users WHERE id = $1")
+                .bind(subscription.created_by)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(|e| format!("Failed to load subscription owner: {}", e))?;
+        match owner {
+            Some((role, true)) if role == "ADMIN" || role == "DOCTOR" => {}
+            _ => return Err("Subscription owner can no longer read reports".to_string()),
+        }
+
+        let request = ExportReportRequest {
+            report_type: subscription.report_type,
+            format: subscription.format,
+            start_date: Some(period.start_date),
+            end_date: Some(period.end_date),
+            provider_id: subscription.filters.provider_id,
+            live: None,
+            compare_to: None,
+        };
+        let export = ReportExportService::new()
+            .generate(
+                &ReportService::new(self.pool.clone()),
+                &request,
+                encryption_key,
+                subscription.created_by,
+            )
+            .await
+            .map_err(|e| format!("Failed to generate report: {:#}", e))?;
+
+        let title = subscription.report_type.display_name();
+        let subject = format!("{}: {} to {}", title, period.start_date, period.end_date);
+        let body = format!(
+            "Please find attached the {} for {} to {}.\n\n\
+             You receive this email because of the report subscription \"{}\".",
+            title, period.start_date, period.end_date, subscription.name
+        );
+
+        let mut failures = Vec::new();
+        for recipient in &subscription.recipients {
+            let result = email_service
+                .send_attachment(
+                    recipient,
+                    recipient,
+                    &subject,
+                    &body,
+                    None,
+                    export.data.clone(),
+                    &export.filename,
+                    &export.content_type,
+                )
+                .await;
+            match result {
+                Ok(result) if result.success => {}
+                Ok(result) => failures.push(format!("{}: {}", recipient, result.message)),
+                Err(e) => failures.push(format!("{}: {}", recipient, e)),
+            }
+        }
+
+        Ok(failures)
+    }
+}
+
+fn to_response(row: ReportSubscription) -> Result<ReportSubscriptionResponse> {
+    ReportSubscriptionResponse::try_from(row).map_err(AppError::Internal)
+}
+
+/// Spawn the delivery of due report subscriptions as a background task
+///
+/// Checks every five minutes; a subscription is due from the start of its
+/// next period (see `ReportCadence::next_run_after`).
+pub fn spawn_report_subscription_job(
+    pool: PgPool,
+    email_service: EmailService,
+    encryption_key: Option<EncryptionKey>,
+) {
+    let service = ReportSubscriptionService::new(pool);
+
+    tokio::spawn(async move {
+        loop {
+            match service
+                .run_due(&email_service, encryption_key.as_ref())
+                .await
+            {
+                Ok(0) => {}
+                Ok(count) => info!("Delivered {} report subscriptions", count),
+                Err(e) => error!("Report subscription job failed: {}", e),
+            }
+
+            sleep(TokioDuration::from_secs(CHECK_INTERVAL_SECS)).await;
+        }
+    });
+
+    info!("Report subscription job spawned as background task");
+}
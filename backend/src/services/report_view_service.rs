@@ -0,0 +1,156 @@
+/*!
+ * Report View Refresh
+ *
+ * Refreshes the materialized views behind the appointment utilization,
+ * patient statistics and diagnosis trends reports, and records each refresh
+ * in `report_view_refreshes`. ReportService reads a view only while its
+ * last refresh is recent enough; otherwise it computes the report live.
+ *
+ * With several replicas each view is refreshed by one instance at a time
+ * (transaction-scoped advisory lock); the others skip it.
+ */
+
+use crate::db::rls::{apply_rls_context, SYSTEM_ROLE, SYSTEM_USER_ID};
+use crate::services::SettingsService;
+use anyhow::{Context, Result};
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::time::{sleep, Duration as TokioDuration};
+use tracing::{error, info};
+
+/// Default minutes between refreshes
+const DEFAULT_REFRESH_MINUTES: i64 = 15;
+
+/// Appointments per day, hour, provider, type and status
+pub const APPOINTMENT_STATS_VIEW: &str = "report_appointment_stats";
+
+/// Patients registered per day
+pub const PATIENT_REGISTRATIONS_VIEW: &str = "report_patient_registrations";
+
+/// Diagnoses per visit date and ICD-10 code
+pub const DIAGNOSIS_STATS_VIEW: &str = "report_diagnosis_stats";
+
+/// Every report view, in refresh order
+pub const REPORT_VIEWS: [&str; 3] = [
+    APPOINTMENT_STATS_VIEW,
+    PATIENT_REGISTRATIONS_VIEW,
+    DIAGNOSIS_STATS_VIEW,
+];
+
+/// Report view refresh service
+pub struct ReportViewService {
+    pool: PgPool,
+}
+
+impl ReportViewService {
+    /// Create a new report view service
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Refresh every report view, returning how many were refreshed here
+    ///
+    /// A view stays usable for two refresh intervals, so one failed or
+    /// skipped run does not send reports to the live queries.
+    pub async fn refresh_all(&self, interval_minutes: i64) -> Result<usize> {
+        let mut refreshed = 0;
+        for view in REPORT_VIEWS {
+            if self.refresh(view, interval_minutes * 2).await? {
+                refreshed += 1;
+            }
+        }
+        Ok(refreshed)
+    }
+
+    /// Refresh one view, unless another instance is refreshing it
+    async fn refresh(&self, view: &'static str, usable_minutes: i64) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+        apply_rls_context(&mut tx, SYSTEM_USER_ID, SYSTEM_ROLE).await?;
+
+        let locked: bool = sqlx::query_scalar("SELECT pg_try_advisory_xact_lock(hashtext($1))")
+            .bind(view)
+            .fetch_one(&mut *tx)
+            .await?;
+        if !locked {
+            return Ok(false);
+        }
+
+        // CONCURRENTLY keeps the view readable during the refresh, but is
+        // not allowed before the first (plain) refresh populates it
+        let populated: bool =
+            sqlx::query_scalar("SELECT ispopulated FROM pg_matviews WHERE matviewname = $1")
+                .bind(view)
+                .fetch_one(&mut *tx)
+                .await
+                .with_context(|| format!("Materialized view {} not found", view))?;
+
+        let started = Instant::now();
+        let sql = if populated {
+            format!("REFRESH MATERIALIZED VIEW CONCURRENTLY {}", view)
+        } else {
+            format!("REFRESH MATERIALIZED VIEW {}", view)
+        };
+        sqlx::query(&sql)
+            .execute(&mut *tx)
+            .await
+            .with_context(|| format!("Failed to refresh {}", view))?;
+        let duration_ms = started.elapsed().as_millis() as i64;
+
+        sqlx::query(
+            r#"
+            INSERT INTO report_view_refreshes (view_name, refreshed_at, duration_ms, stale_after)
+            VALUES ($1, NOW(), $2, NOW() + make_interval(mins => $3))
+            ON CONFLICT (view_name) DO UPDATE
+            SET refreshed_at = EXCLUDED.refreshed_at,
+                duration_ms = EXCLUDED.duration_ms,
+                stale_after = EXCLUDED.stale_after
+            "#,
+        )
+        .bind(view)
+        .bind(duration_ms)
+        .bind(usable_minutes as i32)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(true)
+    }
+}
+
+/// Spawn the refresh of the report views as a background task
+///
+/// Refreshes once at startup, then every `report_views_refresh_minutes`
+/// (read before each run); 0 disables the refresh, so reports are computed
+/// live once the views go stale.
+pub fn spawn_report_view_refresh(pool: PgPool, settings_service: Arc<SettingsService>) {
+    let service = ReportViewService::new(pool);
+
+    tokio::spawn(async move {
+        loop {
+            let interval_minutes = match settings_service
+                .get_setting("report_views_refresh_minutes")
+                .await
+            {
+                Ok(Some(setting)) => setting
+                    .setting_value
+                    .as_i64()
+                    .unwrap_or(DEFAULT_REFRESH_MINUTES),
+                _ => DEFAULT_REFRESH_MINUTES,
+            };
+
+            if interval_minutes <= 0 {
+                sleep(TokioDuration::from_secs(300)).await;
+                continue;
+            }
+
+            if let Err(e) = service.refresh_all(interval_minutes).await {
+                error!("Report view refresh failed: {:#}", e);
+            }
+
+            sleep(TokioDuration::from_secs(interval_minutes as u64 * 60)).await;
+        }
+    });
+
+    info!("Report view refresh spawned as background task");
+}
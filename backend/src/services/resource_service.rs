@@ -0,0 +1,237 @@
+/*!
+ * Resource Service
+ *
+ * Rooms and equipment that appointments reserve. `AppointmentService`
+ * checks and saves the reservations of an appointment in its own
+ * transaction; the calendar of a resource is read through
+ * `resource_busy_intervals()`, which sees the appointments of every
+ * provider but returns no patient data.
+ */
+
+use std::collections::HashMap;
+
+use crate::{
+    models::{
+        CreateResourceRequest, Resource, ResourceBooking, ResourceQuery, UpdateResourceRequest,
+    },
+    utils::{AppError, Result},
+};
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgPool};
+use tracing::info;
+use uuid::Uuid;
+
+const RESOURCE_COLUMNS: &str = r#"
+    id, name, resource_type, description, is_active,
+    created_by, updated_by, created_at, updated_at
+"#;
+
+/// Resource service
+pub struct ResourceService {
+    pool: PgPool,
+}
+
+impl ResourceService {
+    /// Create a new resource service
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Resources by name, active ones only unless `include_inactive`
+    pub async fn list_resources(&self, query: &ResourceQuery) -> Result<Vec<Resource>> {
+        let resources = sqlx::query_as::<_, Resource>(&format!(
+            r#"
+            SELECT {}
+            FROM resources
+            WHERE is_active OR $1
+            ORDER BY LOWER(name)
+            "#,
+            RESOURCE_COLUMNS
+        ))
+        .bind(query.include_inactive)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(resources)
+    }
+
+    /// Add a resource
+    pub async fn create_resource(
+        &self,
+        request: &CreateResourceRequest,
+        created_by: Uuid,
+    ) -> Result<Resource> {
+        let resource = sqlx::query_as::<_, Resource>(&format!(
+            r#"
+            INSERT INTO resources (name, resource_type, description, created_by, updated_by)
+            VALUES ($1, $2, $3, $4, $4)
+            RETURNING {}
+            "#,
+            RESOURCE_COLUMNS
+        ))
+        .bind(request.name.trim())
+        .bind(request.resource_type)
+        .bind(&request.description)
+        .bind(created_by)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| name_taken(e, request.name.trim()))?;
+
+        info!(
+            "Resource {} ({}) created by {}",
+            resource.id, resource.name, created_by
+        );
+        Ok(resource)
+    }
+
+    /// Change a resource
+    ///
+    /// Deactivating a resource keeps its reservations; it can no longer be
+    /// reserved.
+    pub async fn update_resource(
+        &self,
+        id: Uuid,
+        request: &UpdateResourceRequest,
+        updated_by: Uuid,
+    ) -> Result<Resource> {
+        let name = request.name.as_deref().map(str::trim);
+        let resource = sqlx::query_as::<_, Resource>(&format!(
+            r#"
+            UPDATE resources SET
+                name = COALESCE($2, name),
+                resource_type = COALESCE($3, resource_type),
+                description = COALESCE($4, description),
+                is_active = COALESCE($5, is_active),
+                updated_by = $6
+            WHERE id = $1
+            RETURNING {}
+            "#,
+            RESOURCE_COLUMNS
+        ))
+        .bind(id)
+        .bind(name)
+        .bind(request.resource_type)
+        .bind(&request.description)
+        .bind(request.is_active)
+        .bind(updated_by)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| name_taken(e, name.unwrap_or_default()))?
+        .ok_or_else(|| AppError::NotFound(format!("Resource {} not found", id)))?;
+
+        info!("Resource {} updated by {}", id, updated_by);
+        Ok(resource)
+    }
+}
+
+/// Conflict for a name already used by another resource
+fn name_taken(error: sqlx::Error, name: &str) -> AppError {
+    match &error {
+        sqlx::Error::Database(db) if db.is_unique_violation() => {
+            AppError::Conflict(format!("A resource named '{}' already exists", name))
+        }
+        _ => error.into(),
+    }
+}
+
+/// Names of the resources to reserve, read in the caller's transaction
+///
+/// Fails when one does not exist or is inactive.
+pub(crate) async fn bookable_resources(
+    conn: &mut PgConnection,
+    resource_ids: &[Uuid],
+) -> Result<HashMap<Uuid, String>> {
+    if resource_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let rows: Vec<(Uuid, String, bool)> =
+        sqlx::query_as("SELECT id, name, is_active FROM resources WHERE id = ANY($1)")
+            .bind(resource_ids)
+            .fetch_all(&mut *conn)
+            .await?;
+
+    for id in resource_ids {
+        match rows.iter().find(|(row_id, _, _)| row_id == id) {
+            None => return Err(AppError::Validation(format!("Resource {} not found", id))),
+            Some((_, name, false)) => {
+                return Err(AppError::Validation(format!(
+                    "Resource '{}' is not active",
+                    name
+                )))
+            }
+            Some(_) => {}
+        }
+    }
+
+    Ok(rows.into_iter().map(|(id, name, _)| (id, name)).collect())
+}
+
+/// Reservations of resources by other appointments overlapping a time
+pub(crate) async fn resource_bookings(
+    conn: &mut PgConnection,
+    resource_ids: &[Uuid],
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    exclude_appointment_id: Option<Uuid>,
+) -> Result<Vec<ResourceBooking>> {
+    if resource_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let bookings = sqlx::query_as::<_, ResourceBooking>(
+        r#"
+        SELECT resource_id, appointment_id, scheduled_start, scheduled_end
+        FROM resource_busy_intervals($1, $2, $3, $4)
+        "#,
+    )
+    .bind(resource_ids)
+    .bind(from)
+    .bind(to)
+    .bind(exclude_appointment_id)
+    .fetch_all(conn)
+    .await?;
+
+    Ok(bookings)
+}
+
+/// Resources reserved by an appointment
+pub(crate) async fn appointment_resource_ids(
+    conn: &mut PgConnection,
+    appointment_id: Uuid,
+) -> Result<Vec<Uuid>> {
+    let ids = sqlx::query_scalar(
+        "SELECT resource_id FROM appointment_resources WHERE appointment_id = $1",
+    )
+    .bind(appointment_id)
+    .fetch_all(conn)
+    .await?;
+
+    Ok(ids)
+}
+
+/// Replace the resources reserved by an appointment
+pub(crate) async fn set_appointment_resources(
+    conn: &mut PgConnection,
+    appointment_id: Uuid,
+    resource_ids: &[Uuid],
+) -> Result<()> {
+    sqlx::query("DELETE FROM appointment_resources WHERE appointment_id = $1")
+        .bind(appointment_id)
+        .execute(&mut *conn)
+        .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO appointment_resources (appointment_id, resource_id)
+        SELECT $1, UNNEST($2::UUID[])
+        ON CONFLICT DO NOTHING
+        "#,
+    )
+    .bind(appointment_id)
+    .bind(resource_ids)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
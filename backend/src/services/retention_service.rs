@@ -0,0 +1,656 @@
+/*!
+ * Data Retention Service
+ *
+ * Applies the retention rules in `retention_rules`: records older than a
+ * rule's retention period are purged, one rule at a time.
+ * - GENERATED_DOCUMENTS: document rows are deleted, then their PDFs
+ * - AUDIT_LOGS: monthly partitions that ended before the cutoff are dropped
+ *   (audit rows themselves are immutable); the hash chain records where the
+ *   retained chain starts
+ * - NOTIFICATIONS: SENT, FAILED and CANCELLED notifications are deleted
+ * - PATIENT_DATA_EXPORTS: FAILED and EXPIRED export records are deleted
+ * - RECYCLE_BIN: prescriptions, visits and patients deleted before the
+ *   cutoff are removed for good; a patient stays in the bin while any
+ *   clinical record still refers to it
+ *
+ * A dry run counts the records past retention without touching them. Every
+ * rule execution, dry or not, is stored in `retention_runs` and written to
+ * the audit log.
+ *
+ * Enabled rules run on a schedule (`retention_job_interval_hours`, default
+ * 24); admins can also trigger runs via `POST /api/v1/retention-rules/run`.
+ */
+
+use crate::db::rls::{apply_rls_context, include_trashed_records, SYSTEM_ROLE, SYSTEM_USER_ID};
+use crate::models::{
+    AuditAction, AuditLog, CreateAuditLog, EntityType, ListRetentionRunsQuery, RetentionEntityType,
+    RetentionRule, RetentionRuleResponse, RetentionRun, RetentionRunStatus,
+    UpdateRetentionRuleRequest,
+};
+use crate::services::SettingsService;
+use crate::utils::{AppError, Result};
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
+use std::sync::Arc;
+use tokio::time::{sleep, Duration as TokioDuration};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// Rows deleted per transaction
+const PURGE_BATCH_SIZE: i64 = 1000;
+
+/// Default hours between scheduled runs
+const DEFAULT_INTERVAL_HOURS: i64 = 24;
+
+const RULE_COLUMNS: &str = r#"
+    id, entity_type, retention_days, is_enabled, dry_run, description,
+    last_run_at, created_at, updated_at, updated_by
+"#;
+
+const RUN_COLUMNS: &str = r#"
+    id, rule_id, entity_type, dry_run, cutoff, eligible_count, purged_count,
+    status, error_message, triggered_by, started_at, completed_at
+"#;
+
+/// Patient `p` deleted before the cutoff ($1) that no clinical record,
+/// erasure request or merged duplicate refers to
+const PURGEABLE_TRASHED_PATIENT: &str = r#"
+    p.deleted_at < $1
+    AND NOT EXISTS (SELECT 1 FROM visits WHERE patient_id = p.id)
+    AND NOT EXISTS (SELECT 1 FROM prescriptions WHERE patient_id = p.id)
+    AND NOT EXISTS (SELECT 1 FROM appointments WHERE patient_id = p.id)
+    AND NOT EXISTS (SELECT 1 FROM generated_documents WHERE patient_id = p.id)
+    AND NOT EXISTS (SELECT 1 FROM lab_orders WHERE patient_id = p.id)
+    AND NOT EXISTS (SELECT 1 FROM lab_results WHERE patient_id = p.id)
+    AND NOT EXISTS (SELECT 1 FROM imaging_orders WHERE patient_id = p.id)
+    AND NOT EXISTS (SELECT 1 FROM referrals WHERE patient_id = p.id)
+    AND NOT EXISTS (SELECT 1 FROM patient_erasure_requests WHERE patient_id = p.id)
+    AND NOT EXISTS (SELECT 1 FROM patients m WHERE m.merged_into_id = p.id)
+"#;
+
+/// Records past retention and records actually purged
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct PurgeOutcome {
+    eligible: i64,
+    purged: i64,
+}
+
+/// Data retention service
+pub struct RetentionService {
+    pool: PgPool,
+}
+
+impl RetentionService {
+    /// Create a new retention service
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    // ==================== Rules ====================
+
+    /// List all retention rules
+    pub async fn list_rules(&self) -> Result<Vec<RetentionRuleResponse>> {
+        let rules = sqlx::query_as::<_, RetentionRule>(&format!(
+            "SELECT {} FROM retention_rules ORDER BY entity_type",
+            RULE_COLUMNS
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
+        rules.into_iter().map(to_response).collect()
+    }
+
+    /// Update a rule's retention period, enabled flag or dry-run mode
+    pub async fn update_rule(
+        &self,
+        rule_id: Uuid,
+        req: &UpdateRetentionRuleRequest,
+        updated_by: Uuid,
+    ) -> Result<RetentionRuleResponse> {
+        let rule = self.get_rule(rule_id).await?;
+        let entity_type = parse_entity_type(&rule.entity_type)?;
+
+        if let Some(days) = req.retention_days {
+            let min_days = entity_type.min_retention_days();
+            if days < min_days {
+                return Err(AppError::Validation(format!(
+                    "{} must be retained for at least {} days",
+                    entity_type.as_str(),
+                    min_days
+                )));
+            }
+        }
+
+        let updated = sqlx::query_as::<_, RetentionRule>(&format!(
+            r#"
+            UPDATE retention_rules
+            SET retention_days = COALESCE($2, retention_days),
+                is_enabled = COALESCE($3, is_enabled),
+                dry_run = COALESCE($4, dry_run),
+                updated_at = NOW(),
+                updated_by = $5
+            WHERE id = $1
+            RETURNING {}
+            "#,
+            RULE_COLUMNS
+        ))
+        .bind(rule_id)
+        .bind(req.retention_days)
+        .bind(req.is_enabled)
+        .bind(req.dry_run)
+        .bind(updated_by)
+        .fetch_one(&self.pool)
+        .await?;
+
+        to_response(updated)
+    }
+
+    /// List past runs, newest first
+    pub async fn list_runs(&self, query: &ListRetentionRunsQuery) -> Result<Vec<RetentionRun>> {
+        let runs = sqlx::query_as::<_, RetentionRun>(&format!(
+            r#"
+            SELECT {}
+            FROM retention_runs
+            WHERE ($1::TEXT IS NULL OR entity_type = $1)
+              AND ($2::BOOLEAN IS NULL OR dry_run = $2)
+            ORDER BY started_at DESC
+            LIMIT $3
+            "#,
+            RUN_COLUMNS
+        ))
+        .bind(query.entity_type.map(|t| t.as_str()))
+        .bind(query.dry_run)
+        .bind(query.limit())
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(runs)
+    }
+
+    async fn get_rule(&self, rule_id: Uuid) -> Result<RetentionRule> {
+        sqlx::query_as::<_, RetentionRule>(&format!(
+            "SELECT {} FROM retention_rules WHERE id = $1",
+            RULE_COLUMNS
+        ))
+        .bind(rule_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Retention rule {} not found", rule_id)))
+    }
+
+    // ==================== Execution ====================
+
+    /// Run rules on demand
+    ///
+    /// A dry run covers every rule (or the one named), enabled or not, so
+    /// rules can be previewed before they are enabled. A real run covers
+    /// enabled rules only.
+    pub async fn run_now(
+        &self,
+        dry_run: bool,
+        entity_type: Option<RetentionEntityType>,
+        triggered_by: Uuid,
+    ) -> Result<Vec<RetentionRun>> {
+        let rules = sqlx::query_as::<_, RetentionRule>(&format!(
+            r#"
+            SELECT {}
+            FROM retention_rules
+            WHERE ($1::TEXT IS NULL OR entity_type = $1)
+            ORDER BY entity_type
+            "#,
+            RULE_COLUMNS
+        ))
+        .bind(entity_type.map(|t| t.as_str()))
+        .fetch_all(&self.pool)
+        .await?;
+
+        if let (Some(entity_type), false) = (entity_type, dry_run) {
+            if rules.iter().any(|rule| !rule.is_enabled) {
+                return Err(AppError::Conflict(format!(
+                    "Retention rule {} is disabled; enable it or run it as a dry run",
+                    entity_type.as_str()
+                )));
+            }
+        }
+
+        let mut runs = Vec::new();
+        for rule in rules.iter().filter(|rule| dry_run || rule.is_enabled) {
+            runs.push(self.run_rule(rule, dry_run, Some(triggered_by)).await?);
+        }
+
+        Ok(runs)
+    }
+
+    /// Run every enabled rule in its configured mode
+    pub async fn run_scheduled(&self) -> Result<Vec<RetentionRun>> {
+        let rules = sqlx::query_as::<_, RetentionRule>(&format!(
+            "SELECT {} FROM retention_rules WHERE is_enabled ORDER BY entity_type",
+            RULE_COLUMNS
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut runs = Vec::new();
+        for rule in &rules {
+            runs.push(self.run_rule(rule, rule.dry_run, None).await?);
+        }
+
+        Ok(runs)
+    }
+
+    /// Apply one rule, record the run and write the audit entry
+    ///
+    /// A purge failure is recorded as a FAILED run rather than returned, so
+    /// one failing rule does not stop the others.
+    async fn run_rule(
+        &self,
+        rule: &RetentionRule,
+        dry_run: bool,
+        triggered_by: Option<Uuid>,
+    ) -> Result<RetentionRun> {
+        let started_at = Utc::now();
+        let cutoff = rule.cutoff(started_at);
+        let entity_type = parse_entity_type(&rule.entity_type)?;
+
+        let outcome = match entity_type {
+            RetentionEntityType::GeneratedDocuments => {
+                self.purge_generated_documents(cutoff, dry_run).await
+            }
+            RetentionEntityType::AuditLogs => self.purge_audit_logs(cutoff, dry_run).await,
+            RetentionEntityType::Notifications => self.purge_notifications(cutoff, dry_run).await,
+            RetentionEntityType::PatientDataExports => {
+                self.purge_patient_data_exports(cutoff, dry_run).await
+            }
+            RetentionEntityType::RecycleBin => self.purge_recycle_bin(cutoff, dry_run).await,
+        };
+
+        let (status, outcome, error_message) = match outcome {
+            Ok(outcome) => (RetentionRunStatus::Completed, outcome, None),
+            Err(e) => {
+                error!("Retention rule {} failed: {}", rule.entity_type, e);
+                (
+                    RetentionRunStatus::Failed,
+                    PurgeOutcome::default(),
+                    Some(e.to_string()),
+                )
+            }
+        };
+
+        let run = sqlx::query_as::<_, RetentionRun>(&format!(
+            r#"
+            INSERT INTO retention_runs (
+                rule_id, entity_type, dry_run, cutoff, eligible_count, purged_count,
+                status, error_message, triggered_by, started_at, completed_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, NOW())
+            RETURNING {}
+            "#,
+            RUN_COLUMNS
+        ))
+        .bind(rule.id)
+        .bind(&rule.entity_type)
+        .bind(dry_run)
+        .bind(cutoff)
+        .bind(outcome.eligible)
+        .bind(outcome.purged)
+        .bind(status.as_str())
+        .bind(&error_message)
+        .bind(triggered_by)
+        .bind(started_at)
+        .fetch_one(&self.pool)
+        .await?;
+
+        sqlx::query("UPDATE retention_rules SET last_run_at = $2 WHERE id = $1")
+            .bind(rule.id)
+            .bind(started_at)
+            .execute(&self.pool)
+            .await?;
+
+        let _ = AuditLog::create(
+            &self.pool,
+            CreateAuditLog {
+                user_id: triggered_by,
+                action: if dry_run {
+                    AuditAction::Read
+                } else {
+                    AuditAction::Delete
+                },
+                entity_type: EntityType::RetentionRule,
+                entity_id: Some(rule.id.to_string()),
+                changes: Some(serde_json::json!({
+                    "run_id": run.id,
+                    "entity_type": rule.entity_type,
+                    "retention_days": rule.retention_days,
+                    "cutoff": cutoff,
+                    "dry_run": dry_run,
+                    "eligible_count": run.eligible_count,
+                    "purged_count": run.purged_count,
+                    "status": run.status,
+                    "error_message": run.error_message,
+                })),
+                ip_address: None,
+                user_agent: None,
+                request_id: None,
+            },
+        )
+        .await;
+
+        if !dry_run && run.purged_count > 0 {
+            info!(
+                "Retention rule {} purged {} records older than {}",
+                rule.entity_type, run.purged_count, cutoff
+            );
+        }
+
+        Ok(run)
+    }
+
+    // ==================== Purgers ====================
+
+    /// Delete generated documents created before the cutoff, then their PDFs
+    async fn purge_generated_documents(
+        &self,
+        cutoff: DateTime<Utc>,
+        dry_run: bool,
+    ) -> Result<PurgeOutcome> {
+        let eligible = self
+            .count_as_system(
+                "SELECT COUNT(*) FROM generated_documents WHERE created_at < $1",
+                cutoff,
+            )
+            .await?;
+
+        if dry_run || eligible == 0 {
+            return Ok(PurgeOutcome {
+                eligible,
+                purged: 0,
+            });
+        }
+
+        let mut purged = 0;
+        loop {
+            let mut tx = self.system_tx().await?;
+            let file_paths: Vec<String> = sqlx::query_scalar(
+                r#"
+                DELETE FROM generated_documents
+                WHERE id IN (
+                    SELECT id FROM generated_documents
+                    WHERE created_at < $1
+                    LIMIT $2
+                )
+                RETURNING file_path
+                "#,
+            )
+            .bind(cutoff)
+            .bind(PURGE_BATCH_SIZE)
+            .fetch_all(&mut *tx)
+            .await?;
+            tx.commit().await?;
+
+            for path in &file_paths {
+                match tokio::fs::remove_file(path).await {
+                    Ok(()) => {}
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                    Err(e) => warn!("Failed to remove purged document file {}: {}", path, e),
+                }
+            }
+
+            purged += file_paths.len() as i64;
+            if (file_paths.len() as i64) < PURGE_BATCH_SIZE {
+                break;
+            }
+        }
+
+        Ok(PurgeOutcome { eligible, purged })
+    }
+
+    /// Drop audit log partitions that ended before the cutoff
+    ///
+    /// Rows in the partition containing the cutoff are kept until the whole
+    /// month is past retention.
+    async fn purge_audit_logs(&self, cutoff: DateTime<Utc>, dry_run: bool) -> Result<PurgeOutcome> {
+        let partitions: Vec<(String, i64)> = sqlx::query_as(
+            "SELECT partition_name, row_count FROM purge_audit_log_partitions($1, $2)",
+        )
+        .bind(cutoff)
+        .bind(dry_run)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let eligible: i64 = partitions.iter().map(|(_, rows)| *rows).sum();
+
+        for (name, rows) in &partitions {
+            if dry_run {
+                info!(
+                    "Retention dry run: audit log partition {} ({} rows) is past retention",
+                    name, rows
+                );
+            } else {
+                warn!(
+                    "Retention dropped audit log partition {} ({} rows)",
+                    name, rows
+                );
+            }
+        }
+
+        Ok(PurgeOutcome {
+            eligible,
+            purged: if dry_run { 0 } else { eligible },
+        })
+    }
+
+    /// Delete notifications in a final state queued before the cutoff
+    async fn purge_notifications(
+        &self,
+        cutoff: DateTime<Utc>,
+        dry_run: bool,
+    ) -> Result<PurgeOutcome> {
+        self.purge_in_batches(
+            r#"
+            SELECT COUNT(*) FROM notification_queue
+            WHERE status IN ('SENT', 'FAILED', 'CANCELLED') AND created_at < $1
+            "#,
+            r#"
+            DELETE FROM notification_queue
+            WHERE id IN (
+                SELECT id FROM notification_queue
+                WHERE status IN ('SENT', 'FAILED', 'CANCELLED') AND created_at < $1
+                LIMIT $2
+            )
+            "#,
+            cutoff,
+            dry_run,
+        )
+        .await
+    }
+
+    /// Delete finished export records requested before the cutoff
+    ///
+    /// Only FAILED and EXPIRED exports are purged: their bundles are already
+    /// gone from storage.
+    async fn purge_patient_data_exports(
+        &self,
+        cutoff: DateTime<Utc>,
+        dry_run: bool,
+    ) -> Result<PurgeOutcome> {
+        self.purge_in_batches(
+            r#"
+            SELECT COUNT(*) FROM patient_data_exports
+            WHERE status IN ('FAILED', 'EXPIRED') AND created_at < $1
+            "#,
+            r#"
+            DELETE FROM patient_data_exports
+            WHERE id IN (
+                SELECT id FROM patient_data_exports
+                WHERE status IN ('FAILED', 'EXPIRED') AND created_at < $1
+                LIMIT $2
+            )
+            "#,
+            cutoff,
+            dry_run,
+        )
+        .await
+    }
+
+    /// Delete prescriptions, visits and patients moved to the recycle bin
+    /// before the cutoff, in that order
+    ///
+    /// Visits and prescriptions go with their children (diagnoses,
+    /// attachments, ...). A patient is only purged once nothing clinical
+    /// refers to it any more, so a patient whose visits were kept stays in
+    /// the bin.
+    async fn purge_recycle_bin(&self, cutoff: DateTime<Utc>, dry_run: bool) -> Result<PurgeOutcome> {
+        let mut total = PurgeOutcome::default();
+
+        for table in ["prescriptions", "visits"] {
+            let outcome = self
+                .purge_in_batches(
+                    &format!("SELECT COUNT(*) FROM {} WHERE deleted_at < $1", table),
+                    &format!(
+                        "DELETE FROM {} WHERE id IN (SELECT id FROM {} WHERE deleted_at < $1 LIMIT $2)",
+                        table, table
+                    ),
+                    cutoff,
+                    dry_run,
+                )
+                .await?;
+            total.eligible += outcome.eligible;
+            total.purged += outcome.purged;
+        }
+
+        let outcome = self
+            .purge_in_batches(
+                &format!(
+                    "SELECT COUNT(*) FROM patients p WHERE {}",
+                    PURGEABLE_TRASHED_PATIENT
+                ),
+                &format!(
+                    r#"
+                    DELETE FROM patients
+                    WHERE id IN (SELECT p.id FROM patients p WHERE {} LIMIT $2)
+                    "#,
+                    PURGEABLE_TRASHED_PATIENT
+                ),
+                cutoff,
+                dry_run,
+            )
+            .await?;
+        total.eligible += outcome.eligible;
+        total.purged += outcome.purged;
+
+        Ok(total)
+    }
+
+    /// Count rows with `count_sql`, then delete them with `delete_sql` in
+    /// batches of PURGE_BATCH_SIZE unless this is a dry run
+    async fn purge_in_batches(
+        &self,
+        count_sql: &str,
+        delete_sql: &str,
+        cutoff: DateTime<Utc>,
+        dry_run: bool,
+    ) -> Result<PurgeOutcome> {
+        let eligible = self.count_as_system(count_sql, cutoff).await?;
+
+        if dry_run || eligible == 0 {
+            return Ok(PurgeOutcome {
+                eligible,
+                purged: 0,
+            });
+        }
+
+        let mut purged = 0;
+        loop {
+            let mut tx = self.system_tx().await?;
+            let deleted = sqlx::query(delete_sql)
+                .bind(cutoff)
+                .bind(PURGE_BATCH_SIZE)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected() as i64;
+            tx.commit().await?;
+
+            purged += deleted;
+            if deleted < PURGE_BATCH_SIZE {
+                break;
+            }
+        }
+
+        Ok(PurgeOutcome { eligible, purged })
+    }
+
+    async fn count_as_system(&self, sql: &str, cutoff: DateTime<Utc>) -> Result<i64> {
+        let mut tx = self.system_tx().await?;
+        let count: i64 = sqlx::query_scalar(sql)
+            .bind(cutoff)
+            .fetch_one(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(count)
+    }
+
+    /// Transaction acting as the system admin, for RLS-protected tables
+    /// (records in the recycle bin included)
+    async fn system_tx(&self) -> Result<Transaction<'static, Postgres>> {
+        let mut tx = self.pool.begin().await?;
+        apply_rls_context(&mut tx, SYSTEM_USER_ID, SYSTEM_ROLE).await?;
+        include_trashed_records(&mut tx).await?;
+        Ok(tx)
+    }
+}
+
+fn parse_entity_type(entity_type: &str) -> Result<RetentionEntityType> {
+    RetentionEntityType::from_str(entity_type).ok_or_else(|| {
+        AppError::Internal(format!("Unknown retention entity type: {}", entity_type))
+    })
+}
+
+fn to_response(rule: RetentionRule) -> Result<RetentionRuleResponse> {
+    RetentionRuleResponse::try_from(rule).map_err(AppError::Internal)
+}
+
+/// Spawn the scheduled retention job as a background task
+///
+/// The interval is read from `retention_job_interval_hours` before each run;
+/// 0 disables the job until the setting changes. Only enabled rules run, each
+/// in its own dry-run mode.
+pub fn spawn_retention_job(pool: PgPool, settings_service: Arc<SettingsService>) {
+    let service = RetentionService::new(pool);
+
+    tokio::spawn(async move {
+        loop {
+            let interval_hours = match settings_service
+                .get_setting("retention_job_interval_hours")
+                .await
+            {
+                Ok(Some(setting)) => setting
+                    .setting_value
+                    .as_i64()
+                    .unwrap_or(DEFAULT_INTERVAL_HOURS),
+                _ => DEFAULT_INTERVAL_HOURS,
+            };
+
+            if interval_hours <= 0 {
+                sleep(TokioDuration::from_secs(3600)).await;
+                continue;
+            }
+
+            sleep(TokioDuration::from_secs(interval_hours as u64 * 3600)).await;
+
+            match service.run_scheduled().await {
+                Ok(runs) => {
+                    let failed = runs
+                        .iter()
+                        .filter(|run| run.status == RetentionRunStatus::Failed.as_str())
+                        .count();
+                    if failed > 0 {
+                        warn!("Retention job: {} of {} rules failed", failed, runs.len());
+                    }
+                }
+                Err(e) => error!("Retention job failed: {}", e),
+            }
+        }
+    });
+
+    info!("Retention job spawned as background task");
+}
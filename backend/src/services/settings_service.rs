@@ -4,7 +4,7 @@
  * Handles business logic for system settings management including:
  * - CRUD operations for settings
  * - Settings validation by value type
- * - In-memory caching with TTL
+ * - Caching with TTL (in memory, or shared through `ReferenceCache`)
  * - Settings change audit logging
  * - Bulk update operations
  */
@@ -17,85 +17,68 @@ use crate::models::system_setting::{
     SettingGroupInfo, SettingsFilter, SettingValueType, SystemSetting, SystemSettingResponse,
     UpdateSettingRequest,
 };
+use crate::services::reference_cache::{CacheNamespace, ReferenceCache};
 use anyhow::{anyhow, Context, Result};
 use sqlx::PgPool;
-use std::collections::HashMap;
-use std::sync::RwLock;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 use uuid::Uuid;
 
-/// Cache entry with TTL
-struct CacheEntry {
-    value: SystemSettingResponse,
-    expires_at: Instant,
-}
-
 /// Settings Service for managing system configuration
 pub struct SettingsService {
     pool: PgPool,
-    /// In-memory cache for settings
-    cache: RwLock<HashMap<String, CacheEntry>>,
+    /// Cache for settings (in memory unless a shared cache is given)
+    cache: ReferenceCache,
     /// Cache TTL duration
     cache_ttl: Duration,
 }
 
 impl SettingsService {
-    /// Create new settings service with default cache TTL (5 minutes)
+    /// Create new settings service with an in-memory cache and the default
+    /// cache TTL (5 minutes)
     pub fn new(pool: PgPool) -> Self {
-        Self {
-            pool,
-            cache: RwLock::new(HashMap::new()),
-            cache_ttl: Duration::from_secs(300),
-        }
+        Self::with_cache(pool, ReferenceCache::memory())
     }
 
     /// Create new settings service with custom cache TTL
     pub fn with_cache_ttl(pool: PgPool, cache_ttl: Duration) -> Self {
         Self {
             pool,
-            cache: RwLock::new(HashMap::new()),
+            cache: ReferenceCache::memory(),
             cache_ttl,
         }
     }
 
+    /// Create new settings service using the given (possibly shared) cache
+    pub fn with_cache(pool: PgPool, cache: ReferenceCache) -> Self {
+        Self {
+            pool,
+            cache,
+            cache_ttl: CacheNamespace::Settings.ttl(),
+        }
+    }
+
     // ==================== Cache Operations ====================
 
     /// Get setting from cache if not expired
-    fn get_from_cache(&self, key: &str) -> Option<SystemSettingResponse> {
-        let cache = self.cache.read().ok()?;
-        if let Some(entry) = cache.get(key) {
-            if entry.expires_at > Instant::now() {
-                return Some(entry.value.clone());
-            }
-        }
-        None
+    async fn get_from_cache(&self, key: &str) -> Option<SystemSettingResponse> {
+        self.cache.get(CacheNamespace::Settings, key).await
     }
 
     /// Store setting in cache
-    fn set_cache(&self, key: &str, value: SystemSettingResponse) {
-        if let Ok(mut cache) = self.cache.write() {
-            cache.insert(
-                key.to_string(),
-                CacheEntry {
-                    value,
-                    expires_at: Instant::now() + self.cache_ttl,
-                },
-            );
-        }
+    async fn set_cache(&self, key: &str, value: &SystemSettingResponse) {
+        self.cache
+            .set_with_ttl(CacheNamespace::Settings, key, value, self.cache_ttl)
+            .await;
     }
 
     /// Invalidate cache entry for a specific key
-    fn invalidate_cache(&self, key: &str) {
-        if let Ok(mut cache) = self.cache.write() {
-            cache.remove(key);
-        }
+    async fn invalidate_cache(&self, key: &str) {
+        self.cache.invalidate(CacheNamespace::Settings, key).await;
     }
 
     /// Invalidate all cache entries
-    pub fn invalidate_all_cache(&self) {
-        if let Ok(mut cache) = self.cache.write() {
-            cache.clear();
-        }
+    pub async fn invalidate_all_cache(&self) {
+        self.cache.invalidate_all(CacheNamespace::Settings).await;
     }
 
     // ==================== Validation ====================
@@ -206,7 +189,7 @@ impl SettingsService {
     /// Get a single setting by key
     pub async fn get_setting(&self, key: &str) -> Result<Option<SystemSettingResponse>> {
         // Check cache first
-        if let Some(cached) = self.get_from_cache(key) {
+        if let Some(cached) = self.get_from_cache(key).await {
             return Ok(Some(cached));
         }
 
@@ -228,7 +211,7 @@ impl SettingsService {
 
         if let Some(s) = setting {
             let response = SystemSettingResponse::from(s);
-            self.set_cache(key, response.clone());
+            self.set_cache(key, &response).await;
             Ok(Some(response))
         } else {
             Ok(None)
@@ -306,7 +289,7 @@ impl SettingsService {
         .context("Failed to update setting")?;
 
         // Invalidate cache for this key
-        self.invalidate_cache(key);
+        self.invalidate_cache(key).await;
 
         // Log the change for audit (async, don't block response)
         let audit_changes = serde_json::json!({
@@ -331,7 +314,7 @@ impl SettingsService {
         .await;
 
         let response = SystemSettingResponse::from(updated_setting);
-        self.set_cache(key, response.clone());
+        self.set_cache(key, &response).await;
         Ok(response)
     }
 
@@ -429,7 +412,7 @@ impl SettingsService {
         .context("Failed to reset setting")?;
 
         // Invalidate cache
-        self.invalidate_cache(key);
+        self.invalidate_cache(key).await;
 
         // Log the reset for audit
         let audit_changes = serde_json::json!({
@@ -455,7 +438,7 @@ impl SettingsService {
         .await;
 
         let response = SystemSettingResponse::from(updated_setting);
-        self.set_cache(key, response.clone());
+        self.set_cache(key, &response).await;
         Ok(response)
     }
 
@@ -523,7 +506,7 @@ mod tests {
     async fn test_validate_string_type() {
         let service = SettingsService {
             pool: sqlx::PgPool::connect_lazy("postgres://").unwrap(),
-            cache: RwLock::new(HashMap::new()),
+            cache: ReferenceCache::memory(),
             cache_ttl: Duration::from_secs(300),
         };
 
@@ -540,7 +523,7 @@ mod tests {
     async fn test_validate_integer_type() {
         let service = SettingsService {
             pool: sqlx::PgPool::connect_lazy("postgres://").unwrap(),
-            cache: RwLock::new(HashMap::new()),
+            cache: ReferenceCache::memory(),
             cache_ttl: Duration::from_secs(300),
         };
 
@@ -557,7 +540,7 @@ mod tests {
     async fn test_validate_boolean_type() {
         let service = SettingsService {
             pool: sqlx::PgPool::connect_lazy("postgres://").unwrap(),
-            cache: RwLock::new(HashMap::new()),
+            cache: ReferenceCache::memory(),
             cache_ttl: Duration::from_secs(300),
         };
 
@@ -577,7 +560,7 @@ mod tests {
     async fn test_validate_array_type() {
         let service = SettingsService {
             pool: sqlx::PgPool::connect_lazy("postgres://").unwrap(),
-            cache: RwLock::new(HashMap::new()),
+            cache: ReferenceCache::memory(),
             cache_ttl: Duration::from_secs(300),
         };
 
@@ -0,0 +1,489 @@
+/*!
+ * SIEM Exporter
+ *
+ * Streams security events to an external SIEM configured with SIEM_ENDPOINT:
+ * - Authentication failures (401 responses)
+ * - Account lockouts after too many failed logins
+ * - Permission denials (403 responses)
+ * - Audit events (handler-written audit rows and domain events)
+ *
+ * Events go to syslog (UDP or TCP, RFC 5424) as CEF or JSON, or are POSTed
+ * in batches to an HTTP collector. Audit `changes` payloads are never sent,
+ * so no clinical data leaves the system this way.
+ *
+ * Request handling never waits on the SIEM: `emit` only pushes onto a
+ * bounded in-memory queue drained by a background task. While the SIEM is
+ * down the task retries the current batch with exponential backoff and the
+ * queue absorbs new events; once it is full further events are dropped (and
+ * counted in the logs) instead of slowing requests down.
+ */
+
+use crate::config::{SiemConfig, SiemFormat, SiemTransport};
+use anyhow::{Context, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpStream, UdpSocket};
+use tokio::sync::mpsc;
+use tokio::time::{sleep, timeout};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Process-wide exporter, set once at startup when a SIEM is configured
+static EXPORTER: OnceLock<SiemHandle> = OnceLock::new();
+
+/// Events sent per write or HTTP request
+const MAX_BATCH_SIZE: usize = 100;
+
+/// Upper bound for the retry delay while the SIEM is unreachable
+const MAX_BACKOFF_SECS: u64 = 60;
+
+/// Timeout for connecting to and writing to the SIEM
+const SEND_TIMEOUT_SECS: u64 = 10;
+
+/// Syslog facility for security/authorization messages (RFC 5424)
+const SYSLOG_FACILITY: u8 = 4;
+
+/// Kind of security event
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SecurityEventKind {
+    AuthFailure,
+    AccountLockout,
+    PermissionDenied,
+    Audit,
+}
+
+impl SecurityEventKind {
+    /// Identifier used as CEF signature ID and syslog MSGID
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::AuthFailure => "AUTH_FAILURE",
+            Self::AccountLockout => "ACCOUNT_LOCKOUT",
+            Self::PermissionDenied => "PERMISSION_DENIED",
+            Self::Audit => "AUDIT",
+        }
+    }
+
+    /// Human-readable event name
+    fn name(&self) -> &'static str {
+        match self {
+            Self::AuthFailure => "Authentication failure",
+            Self::AccountLockout => "Account locked",
+            Self::PermissionDenied => "Permission denied",
+            Self::Audit => "Audit event",
+        }
+    }
+
+    /// CEF severity (0-10)
+    fn cef_severity(&self) -> u8 {
+        match self {
+            Self::AuthFailure => 5,
+            Self::AccountLockout => 8,
+            Self::PermissionDenied => 6,
+            Self::Audit => 3,
+        }
+    }
+
+    /// Syslog severity (RFC 5424: 4 warning, 5 notice, 6 informational)
+    fn syslog_severity(&self) -> u8 {
+        match self {
+            Self::AuthFailure => 5,
+            Self::AccountLockout | Self::PermissionDenied => 4,
+            Self::Audit => 6,
+        }
+    }
+}
+
+/// Security event streamed to the SIEM
+#[derive(Debug, Clone, Serialize)]
+pub struct SecurityEvent {
+    pub kind: SecurityEventKind,
+    pub timestamp: DateTime<Utc>,
+    /// Audit action, event name, or HTTP method and path
+    pub action: String,
+    pub message: String,
+    pub user_id: Option<Uuid>,
+    pub username: Option<String>,
+    pub ip_address: Option<String>,
+    pub request_id: Option<Uuid>,
+    pub entity_type: Option<String>,
+    pub entity_id: Option<String>,
+    /// HTTP status of the request, when the event comes from one
+    pub status: Option<u16>,
+}
+
+impl SecurityEvent {
+    /// New event with only the required fields set
+    pub fn new(
+        kind: SecurityEventKind,
+        action: impl Into<String>,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            kind,
+            timestamp: Utc::now(),
+            action: action.into(),
+            message: message.into(),
+            user_id: None,
+            username: None,
+            ip_address: None,
+            request_id: None,
+            entity_type: None,
+            entity_id: None,
+            status: None,
+        }
+    }
+
+    /// ArcSight Common Event Format line
+    fn to_cef(&self) -> String {
+        let mut extension = vec![
+            format!("rt={}", self.timestamp.timestamp_millis()),
+            format!("act={}", cef_value(&self.action)),
+        ];
+        let optional = [
+            ("suid", self.user_id.map(|id| id.to_string())),
+            ("suser", self.username.clone()),
+            ("src", self.ip_address.clone()),
+            ("outcome", self.status.map(|s| s.to_string())),
+            (
+                "cs1Label",
+                self.entity_type.as_ref().map(|_| "entityType".to_string()),
+            ),
+            ("cs1", self.entity_type.clone()),
+            (
+                "cs2Label",
+                self.entity_id.as_ref().map(|_| "entityId".to_string()),
+            ),
+            ("cs2", self.entity_id.clone()),
+            ("cs3Label", self.request_id.map(|_| "requestId".to_string())),
+            ("cs3", self.request_id.map(|id| id.to_string())),
+        ];
+        extension.extend(
+            optional
+                .into_iter()
+                .filter_map(|(key, value)| value.map(|v| format!("{}={}", key, cef_value(&v)))),
+        );
+        extension.push(format!("msg={}", cef_value(&self.message)));
+
+        format!(
+            "CEF:0|DocPat|DocPat Backend|{}|{}|{}|{}|{}",
+            cef_header(env!("CARGO_PKG_VERSION")),
+            self.kind.as_str(),
+            cef_header(self.kind.name()),
+            self.kind.cef_severity(),
+            extension.join(" ")
+        )
+    }
+
+    /// Event in the configured format, without transport framing
+    fn encode(&self, format: SiemFormat) -> String {
+        match format {
+            SiemFormat::Cef => self.to_cef(),
+            SiemFormat::Json => serde_json::to_string(self).unwrap_or_default(),
+        }
+    }
+
+    /// RFC 5424 syslog message carrying the encoded event
+    fn to_syslog(&self, format: SiemFormat, hostname: &str) -> String {
+        format!(
+            "<{}>1 {} {} docpat - {} - {}",
+            SYSLOG_FACILITY * 8 + self.kind.syslog_severity(),
+            self.timestamp.to_rfc3339_opts(SecondsFormat::Millis, true),
+            syslog_hostname(hostname),
+            self.kind.as_str(),
+            self.encode(format)
+        )
+    }
+}
+
+/// Escape a CEF header field
+fn cef_header(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace(['\r', '\n'], " ")
+}
+
+/// Escape a CEF extension value
+fn cef_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('=', "\\=")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+/// Syslog HOSTNAME field: printable ASCII without spaces, "-" when empty
+fn syslog_hostname(hostname: &str) -> String {
+    let cleaned: String = hostname
+        .chars()
+        .filter(|c| c.is_ascii_graphic())
+        .take(255)
+        .collect();
+    if cleaned.is_empty() {
+        "-".to_string()
+    } else {
+        cleaned
+    }
+}
+
+/// Queue shared by all emitters
+struct SiemHandle {
+    sender: mpsc::Sender<SecurityEvent>,
+    dropped: AtomicU64,
+}
+
+/// Whether security events are being streamed
+///
+/// Lets callers skip building events when no SIEM is configured.
+pub fn is_enabled() -> bool {
+    EXPORTER.get().is_some()
+}
+
+/// Queue a security event for the SIEM
+///
+/// Never blocks: when no SIEM is configured this does nothing, and when the
+/// queue is full the event is dropped.
+pub fn emit(event: SecurityEvent) {
+    let Some(handle) = EXPORTER.get() else {
+        return;
+    };
+
+    if let Err(mpsc::error::TrySendError::Full(_)) = handle.sender.try_send(event) {
+        let dropped = handle.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+        // Log at 1, 2, 4, 8, ... drops to avoid flooding the logs during an outage
+        if dropped.is_power_of_two() {
+            warn!(
+                "SIEM event queue is full; {} security events dropped so far",
+                dropped
+            );
+        }
+    }
+}
+
+/// Start streaming security events to the configured SIEM
+///
+/// Spawns the background sender; call once at startup.
+pub fn init_siem_exporter(config: SiemConfig) {
+    let (sender, receiver) = mpsc::channel(config.buffer_size);
+    let handle = SiemHandle {
+        sender,
+        dropped: AtomicU64::new(0),
+    };
+    if EXPORTER.set(handle).is_err() {
+        warn!("SIEM exporter already initialized");
+        return;
+    }
+
+    info!(
+        "Streaming security events to SIEM ({:?}, {:?})",
+        config.transport, config.format
+    );
+
+    tokio::spawn(async move {
+        SiemSink::new(config).run(receiver).await;
+    });
+}
+
+/// Connection to the SIEM
+struct SiemSink {
+    config: SiemConfig,
+    http: Option<reqwest::Client>,
+    tcp: Option<TcpStream>,
+    udp: Option<UdpSocket>,
+}
+
+impl SiemSink {
+    fn new(config: SiemConfig) -> Self {
+        let http = match config.transport {
+            SiemTransport::Http(_) => reqwest::Client::builder()
+                .timeout(Duration::from_secs(SEND_TIMEOUT_SECS))
+                .build()
+                .ok(),
+            _ => None,
+        };
+
+        Self {
+            config,
+            http,
+            tcp: None,
+            udp: None,
+        }
+    }
+
+    /// Drain the queue until the application shuts down
+    async fn run(mut self, mut receiver: mpsc::Receiver<SecurityEvent>) {
+        let mut batch = Vec::with_capacity(MAX_BATCH_SIZE);
+
+        while receiver.recv_many(&mut batch, MAX_BATCH_SIZE).await > 0 {
+            let mut backoff = 1;
+            while let Err(e) = self.send(&batch).await {
+                warn!(
+                    "Failed to send {} security events to SIEM, retrying in {}s: {:#}",
+                    batch.len(),
+                    backoff,
+                    e
+                );
+                sleep(Duration::from_secs(backoff)).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF_SECS);
+            }
+            batch.clear();
+        }
+    }
+
+    /// Send one batch; connection errors drop the connection for the retry
+    async fn send(&mut self, batch: &[SecurityEvent]) -> Result<()> {
+        let result = match self.config.transport.clone() {
+            SiemTransport::Udp(addr) => self.send_udp(&addr, batch).await,
+            SiemTransport::Tcp(addr) => self.send_tcp(&addr, batch).await,
+            SiemTransport::Http(url) => self.send_http(&url, batch).await,
+        };
+
+        if result.is_err() {
+            self.tcp = None;
+            self.udp = None;
+        }
+        result
+    }
+
+    async fn send_udp(&mut self, addr: &str, batch: &[SecurityEvent]) -> Result<()> {
+        if self.udp.is_none() {
+            let target = tokio::net::lookup_host(addr)
+                .await?
+                .next()
+                .with_context(|| format!("SIEM address {} did not resolve", addr))?;
+            let local = if target.is_ipv4() {
+                "0.0.0.0:0"
+            } else {
+                "[::]:0"
+            };
+            let socket = UdpSocket::bind(local).await?;
+            socket.connect(target).await?;
+            self.udp = Some(socket);
+        }
+
+        let socket = self.udp.as_ref().context("UDP socket not connected")?;
+        for event in batch {
+            let message = event.to_syslog(self.config.format, &self.config.hostname);
+            socket.send(message.as_bytes()).await?;
+        }
+        Ok(())
+    }
+
+    async fn send_tcp(&mut self, addr: &str, batch: &[SecurityEvent]) -> Result<()> {
+        if self.tcp.is_none() {
+            let stream = timeout(
+                Duration::from_secs(SEND_TIMEOUT_SECS),
+                TcpStream::connect(addr),
+            )
+            .await
+            .context("Timed out connecting to SIEM")??;
+            self.tcp = Some(stream);
+        }
+
+        // Non-transparent framing: one message per line
+        let mut payload = String::new();
+        for event in batch {
+            let message = event.to_syslog(self.config.format, &self.config.hostname);
+            payload.push_str(&message.replace('\n', " "));
+            payload.push('\n');
+        }
+
+        let stream = self.tcp.as_mut().context("TCP stream not connected")?;
+        timeout(
+            Duration::from_secs(SEND_TIMEOUT_SECS),
+            stream.write_all(payload.as_bytes()),
+        )
+        .await
+        .context("Timed out writing to SIEM")??;
+        Ok(())
+    }
+
+    async fn send_http(&mut self, url: &str, batch: &[SecurityEvent]) -> Result<()> {
+        let client = self.http.as_ref().context("HTTP client unavailable")?;
+
+        let request = match self.config.format {
+            SiemFormat::Json => client.post(url).json(batch),
+            SiemFormat::Cef => {
+                let body = batch
+                    .iter()
+                    .map(|event| event.to_cef())
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                client
+                    .post(url)
+                    .header(reqwest::header::CONTENT_TYPE, "text/plain")
+                    .body(body)
+            }
+        };
+        let request = match self.config.auth_token() {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        };
+
+        request.send().await?.error_for_status()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn event() -> SecurityEvent {
+        SecurityEvent {
+            timestamp: Utc.with_ymd_and_hms(2026, 3, 19, 10, 30, 0).unwrap(),
+            user_id: Some(Uuid::nil()),
+            ip_address: Some("10.0.0.7".to_string()),
+            status: Some(403),
+            ..SecurityEvent::new(
+                SecurityEventKind::PermissionDenied,
+                "DELETE /api/v1/users/42",
+                "Access denied",
+            )
+        }
+    }
+
+    #[test]
+    fn test_cef_escaping() {
+        assert_eq!(cef_header("a|b\\c"), "a\\|b\\\\c");
+        assert_eq!(cef_value("k=v\nnext\\"), "k\\=v\\nnext\\\\");
+        // Pipes need no escaping in extensions
+        assert_eq!(cef_value("a|b"), "a|b");
+    }
+
+    #[test]
+    fn test_cef_format() {
+        let cef = event().to_cef();
+        assert!(cef.starts_with("CEF:0|DocPat|DocPat Backend|"));
+        assert!(cef.contains("|PERMISSION_DENIED|Permission denied|6|"));
+        assert!(cef.contains("act=DELETE /api/v1/users/42"));
+        assert!(cef.contains("suid=00000000-0000-0000-0000-000000000000"));
+        assert!(cef.contains("src=10.0.0.7 outcome=403"));
+        assert!(!cef.contains("suser="));
+        assert!(cef.ends_with("msg=Access denied"));
+    }
+
+    #[test]
+    fn test_syslog_framing() {
+        let line = event().to_syslog(SiemFormat::Json, "clinic server");
+        // facility 4 (auth) * 8 + severity 4 (warning)
+        assert!(line.starts_with(
+            "<36>1 2026-03-19T10:30:00.000Z clinicserver docpat - PERMISSION_DENIED - {"
+        ));
+        assert!(line.contains(r#""kind":"PERMISSION_DENIED""#));
+        assert_eq!(syslog_hostname(""), "-");
+    }
+
+    #[test]
+    fn test_emit_without_exporter_is_noop() {
+        assert!(!is_enabled());
+        emit(event());
+    }
+}
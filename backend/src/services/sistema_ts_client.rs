@@ -0,0 +1,429 @@
+/*!
+ * Sistema TS Client
+ *
+ * SOAP client for the Sistema TS dematerialized prescription services
+ * (ricetta dematerializzata):
+ * - `demInvioPrescritto`: submits a prescription and returns its NRE and
+ *   authentication code
+ * - `demAnnullaPrescritto`: cancels an NRE not yet dispensed
+ *
+ * Calls use HTTP basic auth with the prescriber's credentials. The PIN code
+ * and the patient's fiscal code are encrypted with the public key of the
+ * Sistema TS certificate (RSA PKCS#1 v1.5, base64), as the services require.
+ * Storing NREs and tracking their status is `e_prescription_service`'s job.
+ */
+
+use crate::config::SistemaTsConfig;
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::{DateTime, Utc};
+use chrono_tz::Europe::Rome;
+use rsa::{pkcs8::DecodePublicKey, Pkcs1v15Encrypt, RsaPublicKey};
+use std::sync::Arc;
+
+const SOAP_ENVELOPE_NS: &str = "http://schemas.xmlsoap.org/soap/envelope/";
+const INVIO_NS: &str = "http://invioprescrittorichiesta.xsd.dem.sanita.finanze.it";
+const INVIO_ACTION: &str = "http://invioprescritto.wsdl.dem.sanita.finanze.it/InvioPrescritto";
+const ANNULLA_NS: &str = "http://annullaprescrittorichiesta.xsd.dem.sanita.finanze.it";
+const ANNULLA_ACTION: &str =
+    "http://annullaprescritto.wsdl.dem.sanita.finanze.it/AnnullaPrescritto";
+
+/// Outcome code of a successful call
+const ESITO_OK: &str = "0000";
+
+/// One prescribed product
+#[derive(Debug, Clone)]
+pub struct NreItem<'a> {
+    /// AIC code of the package
+    pub aic_code: &'a str,
+    /// Product description printed on the promemoria
+    pub description: &'a str,
+    /// Number of packages
+    pub quantity: i32,
+}
+
+/// Prescription to submit
+#[derive(Debug, Clone)]
+pub struct NreSubmission<'a> {
+    pub patient_fiscal_code: &'a str,
+    pub exemption_code: Option<&'a str>,
+    pub compiled_at: DateTime<Utc>,
+    pub items: Vec<NreItem<'a>>,
+}
+
+/// NRE assigned by Sistema TS
+#[derive(Debug, Clone, PartialEq)]
+pub struct NreReceipt {
+    pub nre: String,
+    /// Needed to cancel the NRE
+    pub auth_code: String,
+}
+
+/// Result of a call Sistema TS answered
+#[derive(Debug, Clone, PartialEq)]
+pub enum SistemaTsOutcome<T> {
+    Accepted(T),
+    /// Refused, with the Sistema TS error messages
+    Rejected(Vec<String>),
+}
+
+/// Sistema TS dematerialized prescription client
+#[derive(Clone)]
+pub struct SistemaTsClient {
+    http: reqwest::Client,
+    config: Arc<SistemaTsConfig>,
+    public_key: RsaPublicKey,
+}
+
+impl SistemaTsClient {
+    /// Build the client from its configuration
+    pub fn new(config: SistemaTsConfig) -> Result<Self> {
+        let public_key = RsaPublicKey::from_public_key_pem(config.public_key_pem.trim())
+            .context("Invalid Sistema TS public key (expected a PEM public key)")?;
+        let http = reqwest::Client::builder()
+            .timeout(config.timeout)
+            .build()
+            .context("Failed to build Sistema TS HTTP client")?;
+
+        Ok(Self {
+            http,
+            config: Arc::new(config),
+            public_key,
+        })
+    }
+
+    /// Fiscal code of the prescriber the client submits as
+    pub fn prescriber_fiscal_code(&self) -> &str {
+        &self.config.prescriber_fiscal_code
+    }
+
+    /// Whether a status callback carries the configured secret
+    ///
+    /// Callbacks are refused when no secret is configured.
+    pub fn verify_callback_secret(&self, provided: &str) -> bool {
+        match self.config.callback_secret() {
+            Some(secret) => constant_time_eq(secret.as_bytes(), provided.as_bytes()),
+            None => false,
+        }
+    }
+
+    /// Submit a prescription (InvioPrescritto)
+    pub async fn submit(
+        &self,
+        submission: &NreSubmission<'_>,
+    ) -> Result<SistemaTsOutcome<NreReceipt>> {
+        let envelope = build_submission_envelope(
+            &self.config,
+            &self.encrypt(self.config.pincode())?,
+            &self.encrypt(submission.patient_fiscal_code)?,
+            submission,
+        );
+        let response = self
+            .call("demInvioPrescritto", INVIO_ACTION, envelope)
+            .await?;
+
+        parse_submission_response(&response)
+    }
+
+    /// Cancel an NRE (AnnullaPrescritto)
+    pub async fn cancel(&self, nre: &str, auth_code: &str) -> Result<SistemaTsOutcome<()>> {
+        let envelope = build_cancellation_envelope(
+            &self.config,
+            &self.encrypt(self.config.pincode())?,
+            nre,
+            auth_code,
+        );
+        let response = self
+            .call("demAnnullaPrescritto", ANNULLA_ACTION, envelope)
+            .await?;
+
+        parse_cancellation_response(&response)
+    }
+
+    fn encrypt(&self, value: &str) -> Result<String> {
+        let encrypted = self
+            .public_key
+            .encrypt(&mut rand::thread_rng(), Pkcs1v15Encrypt, value.as_bytes())
+            .context("Failed to encrypt value for Sistema TS")?;
+        Ok(BASE64.encode(encrypted))
+    }
+
+    async fn call(&self, service: &str, action: &str, envelope: String) -> Result<String> {
+        let response = self
+            .http
+            .post(format!("{}/{}", self.config.endpoint, service))
+            .basic_auth(&self.config.username, Some(self.config.password()))
+            .header("Content-Type", "text/xml; charset=utf-8")
+            .header("SOAPAction", action)
+            .body(envelope)
+            .send()
+            .await
+            .with_context(|| format!("Sistema TS {} request failed", service))?;
+
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .with_context(|| format!("Failed to read Sistema TS {} response", service))?;
+
+        // SOAP faults come back as 500 with a <faultstring>
+        if !status.is_success() {
+            let fault = extract_tag(&body, "faultstring").unwrap_or_else(|| status.to_string());
+            anyhow::bail!("Sistema TS {} failed: {}", service, fault);
+        }
+
+        Ok(body)
+    }
+}
+
+/// Build the InvioPrescritto SOAP envelope
+///
+/// `encrypted_pin` and `encrypted_patient_cf` are already encrypted with the
+/// Sistema TS public key.
+fn build_submission_envelope(
+    config: &SistemaTsConfig,
+    encrypted_pin: &str,
+    encrypted_patient_cf: &str,
+    submission: &NreSubmission<'_>,
+) -> String {
+    let mut fields = vec![
+        ("pinCode", encrypted_pin.to_string()),
+        ("cfMedico1", config.prescriber_fiscal_code.clone()),
+        ("codRegione", config.region_code.clone()),
+        ("codASLAo", config.asl_code.clone()),
+        ("codSpecializzazione", config.specialization.clone()),
+        ("codiceAss", encrypted_patient_cf.to_string()),
+    ];
+    if let Some(exemption) = submission.exemption_code {
+        fields.push(("codEsenzione", exemption.to_string()));
+    }
+    fields.extend([
+        // Pharmaceutical prescription, outpatient visit
+        ("tipoPrescrizione", "F".to_string()),
+        (
+            "dataCompilazione",
+            submission
+                .compiled_at
+                .with_timezone(&Rome)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string(),
+        ),
+        ("tipoVisita", "A".to_string()),
+    ]);
+
+    let mut body = String::new();
+    for (name, value) in &fields {
+        body.push_str(&format!("<inv:{0}>{1}</inv:{0}>", name, xml_escape(value)));
+    }
+    body.push_str("<inv:ElencoDettagliPrescrizioni>");
+    for item in &submission.items {
+        body.push_str(&format!(
+            "<inv:DettaglioPrescrizione>\
+             <inv:codProdPrest>{}</inv:codProdPrest>\
+             <inv:descrProdPrest>{}</inv:descrProdPrest>\
+             <inv:quantita>{}</inv:quantita>\
+             </inv:DettaglioPrescrizione>",
+            xml_escape(item.aic_code),
+            xml_escape(item.description),
+            item.quantity
+        ));
+    }
+    body.push_str("</inv:ElencoDettagliPrescrizioni>");
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?><soapenv:Envelope xmlns:soapenv="{}" xmlns:inv="{}"><soapenv:Header/><soapenv:Body><inv:InvioPrescrittoRichiesta>{}</inv:InvioPrescrittoRichiesta></soapenv:Body></soapenv:Envelope>"#,
+        SOAP_ENVELOPE_NS, INVIO_NS, body
+    )
+}
+
+/// Build the AnnullaPrescritto SOAP envelope
+fn build_cancellation_envelope(
+    config: &SistemaTsConfig,
+    encrypted_pin: &str,
+    nre: &str,
+    auth_code: &str,
+) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?><soapenv:Envelope xmlns:soapenv="{}" xmlns:ann="{}"><soapenv:Header/><soapenv:Body><ann:AnnullaPrescrittoRichiesta><ann:pinCode>{}</ann:pinCode><ann:cfMedico>{}</ann:cfMedico><ann:nre>{}</ann:nre><ann:codAutenticazione>{}</ann:codAutenticazione></ann:AnnullaPrescrittoRichiesta></soapenv:Body></soapenv:Envelope>"#,
+        SOAP_ENVELOPE_NS,
+        ANNULLA_NS,
+        xml_escape(encrypted_pin),
+        xml_escape(&config.prescriber_fiscal_code),
+        xml_escape(nre),
+        xml_escape(auth_code)
+    )
+}
+
+/// Parse the InvioPrescritto receipt
+fn parse_submission_response(xml: &str) -> Result<SistemaTsOutcome<NreReceipt>> {
+    let esito = extract_tag(xml, "codEsitoInserimento")
+        .context("Sistema TS response has no codEsitoInserimento")?;
+    if esito != ESITO_OK {
+        return Ok(SistemaTsOutcome::Rejected(extract_errors(xml, &esito)));
+    }
+
+    let nre =
+        extract_tag(xml, "nre").context("Sistema TS accepted the prescription without an NRE")?;
+    let auth_code = extract_tag(xml, "codAutenticazione")
+        .context("Sistema TS accepted the prescription without an authentication code")?;
+
+    Ok(SistemaTsOutcome::Accepted(NreReceipt { nre, auth_code }))
+}
+
+/// Parse the AnnullaPrescritto receipt
+fn parse_cancellation_response(xml: &str) -> Result<SistemaTsOutcome<()>> {
+    let esito = extract_tag(xml, "codEsitoAnnullamento")
+        .context("Sistema TS response has no codEsitoAnnullamento")?;
+    if esito != ESITO_OK {
+        return Ok(SistemaTsOutcome::Rejected(extract_errors(xml, &esito)));
+    }
+    Ok(SistemaTsOutcome::Accepted(()))
+}
+
+/// `codEsito: esito` pairs of the Errore elements, or the outcome code alone
+fn extract_errors(xml: &str, esito: &str) -> Vec<String> {
+    let codes = extract_all_tags(xml, "codEsito");
+    let messages = extract_all_tags(xml, "esito");
+    let errors: Vec<String> = codes
+        .iter()
+        .zip(messages.iter())
+        .map(|(code, message)| format!("{}: {}", code, message))
+        .collect();
+
+    if errors.is_empty() {
+        vec![format!("Outcome code {}", esito)]
+    } else {
+        errors
+    }
+}
+
+/// Text of the first element with this local name, whatever its prefix
+fn extract_tag(xml: &str, name: &str) -> Option<String> {
+    extract_all_tags(xml, name).into_iter().next()
+}
+
+fn extract_all_tags(xml: &str, name: &str) -> Vec<String> {
+    let pattern = format!(
+        r"<(?:[A-Za-z0-9_]+:)?{0}(?:\s[^>]*)?>([^<]*)</(?:[A-Za-z0-9_]+:)?{0}>",
+        regex::escape(name)
+    );
+    match regex::Regex::new(&pattern) {
+        Ok(re) => re
+            .captures_iter(xml)
+            .map(|c| xml_unescape(c[1].trim()))
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn xml_unescape(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Compare secrets without leaking the position of the first difference
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn submission() -> NreSubmission<'static> {
+        NreSubmission {
+            patient_fiscal_code: "RSSMRA80A01H501U",
+            exemption_code: Some("013"),
+            compiled_at: Utc.with_ymd_and_hms(2026, 4, 7, 8, 30, 0).unwrap(),
+            items: vec![NreItem {
+                aic_code: "023658012",
+                description: "ZIMOX 500 mg <12 cps>",
+                quantity: 2,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_submission_envelope() {
+        let config = SistemaTsConfig::test_config();
+        let xml = build_submission_envelope(&config, "ENC-PIN", "ENC-CF", &submission());
+
+        assert!(xml.contains("<inv:pinCode>ENC-PIN</inv:pinCode>"));
+        assert!(xml.contains("<inv:codiceAss>ENC-CF</inv:codiceAss>"));
+        assert!(xml.contains("<inv:cfMedico1>BNCGVN70A01F205X</inv:cfMedico1>"));
+        assert!(xml.contains("<inv:codEsenzione>013</inv:codEsenzione>"));
+        // Local Italian time (CEST in April)
+        assert!(xml.contains("<inv:dataCompilazione>2026-04-07 10:30:00</inv:dataCompilazione>"));
+        assert!(xml.contains("<inv:codProdPrest>023658012</inv:codProdPrest>"));
+        assert!(xml.contains("ZIMOX 500 mg &lt;12 cps&gt;"));
+        assert!(xml.contains("<inv:quantita>2</inv:quantita>"));
+        assert!(!xml.contains("RSSMRA80A01H501U"));
+    }
+
+    #[test]
+    fn test_parse_accepted_submission() {
+        let xml = r#"<soap:Envelope><soap:Body><ns2:InvioPrescrittoRicevuta>
+            <ns2:nre>1200A4001234567</ns2:nre>
+            <ns2:codAutenticazione>483920</ns2:codAutenticazione>
+            <ns2:codEsitoInserimento>0000</ns2:codEsitoInserimento>
+            </ns2:InvioPrescrittoRicevuta></soap:Body></soap:Envelope>"#;
+
+        assert_eq!(
+            parse_submission_response(xml).unwrap(),
+            SistemaTsOutcome::Accepted(NreReceipt {
+                nre: "1200A4001234567".to_string(),
+                auth_code: "483920".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_rejected_submission() {
+        let xml = r#"<ns2:InvioPrescrittoRicevuta>
+            <ns2:codEsitoInserimento>9999</ns2:codEsitoInserimento>
+            <ns2:ElencoErroriRicette><ns2:Errore>
+            <ns2:codEsito>5012</ns2:codEsito><ns2:esito>Codice AIC non valido</ns2:esito>
+            </ns2:Errore></ns2:ElencoErroriRicette></ns2:InvioPrescrittoRicevuta>"#;
+
+        assert_eq!(
+            parse_submission_response(xml).unwrap(),
+            SistemaTsOutcome::Rejected(vec!["5012: Codice AIC non valido".to_string()])
+        );
+        assert!(parse_submission_response("<html>bad gateway</html>").is_err());
+    }
+
+    #[test]
+    fn test_parse_cancellation() {
+        assert_eq!(
+            parse_cancellation_response("<codEsitoAnnullamento>0000</codEsitoAnnullamento>")
+                .unwrap(),
+            SistemaTsOutcome::Accepted(())
+        );
+        assert_eq!(
+            parse_cancellation_response("<codEsitoAnnullamento>0001</codEsitoAnnullamento>")
+                .unwrap(),
+            SistemaTsOutcome::Rejected(vec!["Outcome code 0001".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secret2"));
+    }
+}
@@ -0,0 +1,447 @@
+/*!
+ * Telemetry Service
+ *
+ * Opt-in reporting of anonymous operational metrics to help prioritize
+ * development. A report contains only:
+ * - Application version, enabled compile-time features and environment
+ * - Aggregate feature usage counts (appointments, visits, prescriptions,
+ *   documents, notifications created; number of users who logged in)
+ * - Error rates of the notification and document pipelines and counts of
+ *   internal alerts per type
+ *
+ * Nothing is sent unless `telemetry_enabled` is true and `telemetry_endpoint`
+ * is set. `preview` builds the exact payload the next report would carry, and
+ * every report that is posted is stored in `telemetry_reports`.
+ */
+
+use crate::db::rls::{apply_rls_context, SYSTEM_ROLE, SYSTEM_USER_ID};
+use crate::models::{
+    TelemetryErrorRate, TelemetryErrors, TelemetryPayload, TelemetryPreviewResponse,
+    TelemetryReport, TelemetryUsage, TELEMETRY_SCHEMA_VERSION,
+};
+use crate::services::SettingsService;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use sqlx::PgPool;
+use std::sync::Arc;
+use tokio::time::{sleep, Duration as TokioDuration};
+use tracing::{error, info, warn};
+
+/// How often the reporter checks whether a report is due
+const CHECK_INTERVAL_SECS: u64 = 3600;
+
+/// Timeout for posting a report
+const REQUEST_TIMEOUT_SECS: u64 = 30;
+
+/// Telemetry configuration loaded from settings
+#[derive(Debug, Clone)]
+pub struct TelemetryConfig {
+    /// Whether the administrator opted in
+    pub enabled: bool,
+    /// Endpoint reports are posted to
+    pub endpoint: Option<String>,
+    /// Hours between reports
+    pub interval_hours: i64,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            endpoint: None,
+            interval_hours: 24,
+        }
+    }
+}
+
+impl TelemetryConfig {
+    /// Endpoint to post to, if telemetry is enabled and the endpoint is valid
+    pub fn active_endpoint(&self) -> Option<reqwest::Url> {
+        if !self.enabled {
+            return None;
+        }
+        self.endpoint.as_deref().and_then(validate_endpoint)
+    }
+}
+
+/// Parse a telemetry endpoint, accepting HTTPS (or plain HTTP to localhost)
+pub fn validate_endpoint(endpoint: &str) -> Option<reqwest::Url> {
+    let url = reqwest::Url::parse(endpoint.trim()).ok()?;
+    let is_local = matches!(url.host_str(), Some("localhost") | Some("127.0.0.1") | Some("[::1]"));
+
+    match url.scheme() {
+        "https" => Some(url),
+        "http" if is_local => Some(url),
+        _ => None,
+    }
+}
+
+/// Compile-time features enabled in this build
+pub fn enabled_features() -> Vec<String> {
+    let features: [(&str, bool); 8] = [
+        ("email", cfg!(feature = "email")),
+        ("metrics", cfg!(feature = "metrics")),
+        ("pdf-export", cfg!(feature = "pdf-export")),
+        ("rbac", cfg!(feature = "rbac")),
+        ("redis-cache", cfg!(feature = "redis-cache")),
+        ("report-export", cfg!(feature = "report-export")),
+        ("sms", cfg!(feature = "sms")),
+        ("whatsapp", cfg!(feature = "whatsapp")),
+    ];
+
+    features
+        .iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| name.to_string())
+        .collect()
+}
+
+/// Telemetry service
+pub struct TelemetryService {
+    pool: PgPool,
+    settings_service: Arc<SettingsService>,
+    environment: String,
+    client: reqwest::Client,
+}
+
+impl TelemetryService {
+    /// Create a new telemetry service
+    pub fn new(pool: PgPool, settings_service: Arc<SettingsService>, environment: String) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(REQUEST_TIMEOUT_SECS))
+            .build()
+            .unwrap_or_default();
+
+        Self {
+            pool,
+            settings_service,
+            environment,
+            client,
+        }
+    }
+
+    /// Load telemetry configuration from database settings
+    pub async fn load_config(&self) -> TelemetryConfig {
+        let mut config = TelemetryConfig::default();
+
+        if let Ok(Some(setting)) = self.settings_service.get_setting("telemetry_enabled").await {
+            if let Some(value) = setting.setting_value.as_bool() {
+                config.enabled = value;
+            }
+        }
+
+        if let Ok(Some(setting)) = self.settings_service.get_setting("telemetry_endpoint").await {
+            if let Some(value) = setting.setting_value.as_str() {
+                if !value.trim().is_empty() {
+                    config.endpoint = Some(value.trim().to_string());
+                }
+            }
+        }
+
+        if let Ok(Some(setting)) = self
+            .settings_service
+            .get_setting("telemetry_interval_hours")
+            .await
+        {
+            if let Some(value) = setting.setting_value.as_i64() {
+                config.interval_hours = value.max(1);
+            }
+        }
+
+        config
+    }
+
+    /// End of the period covered by the last successfully sent report
+    pub async fn last_sent_at(&self) -> Result<Option<DateTime<Utc>>> {
+        let last_sent = sqlx::query_scalar::<_, Option<DateTime<Utc>>>(
+            r#"
+            SELECT MAX(period_end)
+            FROM telemetry_reports
+            WHERE status_code BETWEEN 200 AND 299
+            "#,
+        )
+        .fetch_one(&self.pool)
+        .await
+        .context("Failed to load last telemetry report")?;
+
+        Ok(last_sent)
+    }
+
+    /// Period the next report covers: since the last sent report (or one
+    /// interval back if nothing was sent yet) up to now
+    fn next_period(
+        last_sent_at: Option<DateTime<Utc>>,
+        interval_hours: i64,
+        now: DateTime<Utc>,
+    ) -> (DateTime<Utc>, DateTime<Utc>) {
+        let start = last_sent_at
+            .filter(|last| *last < now)
+            .unwrap_or_else(|| now - Duration::hours(interval_hours));
+        (start, now)
+    }
+
+    /// Collect the anonymous payload for a period
+    pub async fn collect(
+        &self,
+        period_start: DateTime<Utc>,
+        period_end: DateTime<Utc>,
+    ) -> Result<TelemetryPayload> {
+        let mut tx = self.pool.begin().await?;
+        apply_rls_context(&mut tx, SYSTEM_USER_ID, SYSTEM_ROLE).await?;
+
+        let usage = sqlx::query_as::<_, TelemetryUsage>(
+            r#"
+            SELECT
+                (SELECT COUNT(*) FROM appointments
+                 WHERE created_at >= $1 AND created_at < $2) AS appointments_created,
+                (SELECT COUNT(*) FROM visits
+                 WHERE created_at >= $1 AND created_at < $2) AS visits_created,
+                (SELECT COUNT(*) FROM prescriptions
+                 WHERE created_at >= $1 AND created_at < $2) AS prescriptions_created,
+                (SELECT COUNT(*) FROM generated_documents
+                 WHERE created_at >= $1 AND created_at < $2) AS documents_generated,
+                (SELECT COUNT(*) FROM notification_queue
+                 WHERE created_at >= $1 AND created_at < $2) AS notifications_queued,
+                (SELECT COUNT(*) FROM users
+                 WHERE last_login >= $1 AND last_login < $2) AS active_users
+            "#,
+        )
+        .bind(period_start)
+        .bind(period_end)
+        .fetch_one(&mut *tx)
+        .await
+        .context("Failed to collect usage counts")?;
+
+        let (notifications_failed, documents_failed) = sqlx::query_as::<_, (i64, i64)>(
+            r#"
+            SELECT
+                (SELECT COUNT(*) FROM notification_queue
+                 WHERE created_at >= $1 AND created_at < $2 AND status = 'FAILED'),
+                (SELECT COUNT(*) FROM generated_documents
+                 WHERE created_at >= $1 AND created_at < $2 AND status = 'FAILED')
+            "#,
+        )
+        .bind(period_start)
+        .bind(period_end)
+        .fetch_one(&mut *tx)
+        .await
+        .context("Failed to collect error counts")?;
+
+        let system_alerts = sqlx::query_as::<_, (String, i64)>(
+            r#"
+            SELECT alert_type, COUNT(*)
+            FROM system_alerts
+            WHERE created_at >= $1 AND created_at < $2
+            GROUP BY alert_type
+            "#,
+        )
+        .bind(period_start)
+        .bind(period_end)
+        .fetch_all(&mut *tx)
+        .await
+        .context("Failed to collect system alert counts")?
+        .into_iter()
+        .collect();
+
+        tx.commit().await?;
+
+        Ok(TelemetryPayload {
+            schema_version: TELEMETRY_SCHEMA_VERSION,
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            features: enabled_features(),
+            environment: self.environment.clone(),
+            period_start,
+            period_end,
+            errors: TelemetryErrors {
+                notifications: TelemetryErrorRate::new(usage.notifications_queued, notifications_failed),
+                documents: TelemetryErrorRate::new(usage.documents_generated, documents_failed),
+                system_alerts,
+            },
+            usage,
+        })
+    }
+
+    /// Build the payload the next report would send, without sending it
+    pub async fn preview(&self) -> Result<TelemetryPreviewResponse> {
+        let config = self.load_config().await;
+        let last_sent_at = self.last_sent_at().await?;
+        let (start, end) = Self::next_period(last_sent_at, config.interval_hours, Utc::now());
+        let payload = self.collect(start, end).await?;
+
+        Ok(TelemetryPreviewResponse {
+            enabled: config.enabled,
+            endpoint: config.endpoint,
+            interval_hours: config.interval_hours,
+            last_sent_at,
+            payload,
+        })
+    }
+
+    /// Collect and post a report to the configured endpoint, recording the result
+    pub async fn send_report(&self, endpoint: &reqwest::Url, config: &TelemetryConfig) -> Result<()> {
+        let last_sent_at = self.last_sent_at().await?;
+        let (start, end) = Self::next_period(last_sent_at, config.interval_hours, Utc::now());
+        let payload = self.collect(start, end).await?;
+        let body = serde_json::to_value(&payload)?;
+
+        let (status_code, error) = match self.client.post(endpoint.clone()).json(&body).send().await {
+            Ok(response) if response.status().is_success() => (Some(response.status().as_u16() as i32), None),
+            Ok(response) => (
+                Some(response.status().as_u16() as i32),
+                Some(format!("Endpoint returned {}", response.status())),
+            ),
+            Err(e) => (None, Some(e.to_string())),
+        };
+
+        sqlx::query(
+            r#"
+            INSERT INTO telemetry_reports (period_start, period_end, endpoint, payload, status_code, error)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(start)
+        .bind(end)
+        .bind(endpoint.as_str())
+        .bind(&body)
+        .bind(status_code)
+        .bind(&error)
+        .execute(&self.pool)
+        .await
+        .context("Failed to record telemetry report")?;
+
+        match error {
+            Some(e) => anyhow::bail!("Telemetry report not accepted: {}", e),
+            None => Ok(()),
+        }
+    }
+
+    /// Reports that were sent, most recent first
+    pub async fn list_reports(&self, limit: i64) -> Result<Vec<TelemetryReport>> {
+        let reports = sqlx::query_as::<_, TelemetryReport>(
+            r#"
+            SELECT id, period_start, period_end, endpoint, payload, status_code, error, created_at
+            FROM telemetry_reports
+            ORDER BY created_at DESC
+            LIMIT $1
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to list telemetry reports")?;
+
+        Ok(reports)
+    }
+
+    /// Start the background reporting loop
+    pub async fn start(self: Arc<Self>) {
+        info!("Starting telemetry reporter background task");
+
+        loop {
+            let config = self.load_config().await;
+
+            if let Some(endpoint) = config.active_endpoint() {
+                let due = match self.last_sent_at().await {
+                    Ok(Some(last)) => Utc::now() - last >= Duration::hours(config.interval_hours),
+                    Ok(None) => true,
+                    Err(e) => {
+                        error!("Failed to check telemetry schedule: {}", e);
+                        false
+                    }
+                };
+
+                if due {
+                    match self.send_report(&endpoint, &config).await {
+                        Ok(()) => info!("Anonymous telemetry report sent to {}", endpoint),
+                        Err(e) => warn!("Telemetry report failed: {:#}", e),
+                    }
+                }
+            } else if config.enabled {
+                warn!("Telemetry is enabled but telemetry_endpoint is missing or not HTTPS; nothing sent");
+            }
+
+            sleep(TokioDuration::from_secs(CHECK_INTERVAL_SECS)).await;
+        }
+    }
+}
+
+/// Spawn the telemetry reporter as a background task
+///
+/// The task always runs but only sends reports once telemetry is enabled
+/// in settings, so opting in does not require a restart.
+pub fn spawn_telemetry_reporter(
+    pool: PgPool,
+    settings_service: Arc<SettingsService>,
+    environment: String,
+) {
+    let reporter = Arc::new(TelemetryService::new(pool, settings_service, environment));
+
+    tokio::spawn(async move {
+        reporter.start().await;
+    });
+
+    info!("Telemetry reporter spawned as background task");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_telemetry_disabled_by_default() {
+        let config = TelemetryConfig::default();
+        assert!(!config.enabled);
+        assert!(config.active_endpoint().is_none());
+    }
+
+    #[test]
+    fn test_active_endpoint_requires_opt_in() {
+        let mut config = TelemetryConfig {
+            enabled: false,
+            endpoint: Some("https://telemetry.example.org/v1/report".to_string()),
+            interval_hours: 24,
+        };
+        assert!(config.active_endpoint().is_none());
+
+        config.enabled = true;
+        assert_eq!(
+            config.active_endpoint().unwrap().as_str(),
+            "https://telemetry.example.org/v1/report"
+        );
+    }
+
+    #[test]
+    fn test_validate_endpoint() {
+        assert!(validate_endpoint("https://telemetry.example.org").is_some());
+        assert!(validate_endpoint("http://localhost:9000/report").is_some());
+        assert!(validate_endpoint("http://telemetry.example.org").is_none());
+        assert!(validate_endpoint("ftp://telemetry.example.org").is_none());
+        assert!(validate_endpoint("not a url").is_none());
+    }
+
+    #[test]
+    fn test_next_period() {
+        let now = Utc::now();
+
+        let (start, end) = TelemetryService::next_period(None, 24, now);
+        assert_eq!(end, now);
+        assert_eq!(start, now - Duration::hours(24));
+
+        let last = now - Duration::hours(30);
+        assert_eq!(TelemetryService::next_period(Some(last), 24, now).0, last);
+
+        // A report period ending in the future (clock skew) is ignored
+        let future = now + Duration::hours(1);
+        assert_eq!(
+            TelemetryService::next_period(Some(future), 24, now).0,
+            now - Duration::hours(24)
+        );
+    }
+
+    #[test]
+    fn test_enabled_features_matches_build() {
+        let features = enabled_features();
+        assert_eq!(features.contains(&"rbac".to_string()), cfg!(feature = "rbac"));
+        assert_eq!(features.contains(&"email".to_string()), cfg!(feature = "email"));
+    }
+}
@@ -0,0 +1,410 @@
+/*!
+ * Televisit Service
+ *
+ * Video rooms of TELEVISIT appointments: provisioning a room with the
+ * configured `VideoRoomProvider`, emailing the patient the join link and
+ * recording when doctor and patient join and leave.
+ *
+ * Staff act through their appointment access (the session policies defer
+ * to the appointment policies). The patient acts through the signed link of
+ * the invitation email, as the portal role with app.portal_patient_id set,
+ * which may only record its own attendance.
+ */
+
+use crate::{
+    db::rls::{begin_portal_transaction, begin_with_rls},
+    models::{
+        televisit_join_window, televisit_joinable, AppointmentStatus, AppointmentType, Patient,
+        TelevisitAttendance, TelevisitClaims, TelevisitJoinResponse, TelevisitLeaveResponse,
+        TelevisitParticipant, TelevisitSession,
+    },
+    services::{
+        video_room::{VideoRoomProvider, VideoRoomRequest},
+        BrandingService, NotificationService,
+    },
+    utils::{encryption::EncryptionKey, AppError, Result},
+};
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+const SESSION_COLUMNS: &str = "id, appointment_id, patient_id, video_provider, room_name, \
+    join_url, invitation_notification_id, provider_joined_at, provider_left_at, \
+    patient_joined_at, patient_left_at, created_by, created_at, updated_at";
+
+/// Appointment fields a televisit needs
+#[derive(sqlx::FromRow)]
+struct TelevisitAppointment {
+    patient_id: Uuid,
+    provider_id: Uuid,
+    #[sqlx(rename = "type")]
+    appointment_type: AppointmentType,
+    status: AppointmentStatus,
+    scheduled_start: DateTime<Utc>,
+    scheduled_end: DateTime<Utc>,
+}
+
+/// Televisit service
+pub struct TelevisitService {
+    pool: PgPool,
+}
+
+impl TelevisitService {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Session of a televisit appointment
+    ///
+    /// Answers 404 when the appointment is not visible or has no room yet.
+    pub async fn get_session(
+        &self,
+        appointment_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<TelevisitSession> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        let session = find_session(&mut tx, appointment_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Televisit room not found".to_string()))?;
+        tx.commit().await?;
+
+        Ok(session)
+    }
+
+    /// Provision the video room of a televisit appointment
+    ///
+    /// Returns the room the appointment already has, if any, with `false`.
+    /// Fails with BadRequest when the appointment is not a televisit and with
+    /// Conflict when it is cancelled, missed or completed.
+    pub async fn provision(
+        &self,
+        appointment_id: Uuid,
+        provider: &dyn VideoRoomProvider,
+        user_id: Uuid,
+    ) -> Result<(TelevisitSession, bool)> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        let appointment = find_appointment(&mut tx, appointment_id).await?;
+        if appointment.appointment_type != AppointmentType::Televisit {
+            return Err(AppError::BadRequest(
+                "Only televisit appointments have a video room".to_string(),
+            ));
+        }
+        if appointment.status.is_final() {
+            return Err(AppError::Conflict(
+                "The appointment was cancelled, missed or completed".to_string(),
+            ));
+        }
+        if let Some(session) = find_session(&mut tx, appointment_id).await? {
+            return Ok((session, false));
+        }
+        // No connection is held while the provider is called
+        tx.commit().await?;
+
+        let room = provider
+            .create_room(&VideoRoomRequest {
+                appointment_id,
+                starts_at: appointment.scheduled_start,
+                ends_at: appointment.scheduled_end,
+            })
+            .await
+            .map_err(|e| {
+                tracing::error!(
+                    "Video room provider {} failed for appointment {}: {:#}",
+                    provider.name(),
+                    appointment_id,
+                    e
+                );
+                AppError::Internal("Failed to create the video room".to_string())
+            })?;
+
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        let created = sqlx::query_as::<_, TelevisitSession>(&format!(
+            r#"
+            INSERT INTO televisit_sessions
+                (appointment_id, patient_id, video_provider, room_name, join_url, created_by)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (appointment_id) DO NOTHING
+            RETURNING {}
+            "#,
+            SESSION_COLUMNS
+        ))
+        .bind(appointment_id)
+        .bind(appointment.patient_id)
+        .bind(provider.name())
+        .bind(&room.room_name)
+        .bind(&room.join_url)
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        // Provisioned concurrently: keep the room stored first
+        let result = match created {
+            Some(session) => (session, true),
+            None => (
+                find_session(&mut tx, appointment_id)
+                    .await?
+                    .ok_or_else(|| AppError::NotFound("Televisit room not found".to_string()))?,
+                false,
+            ),
+        };
+        tx.commit().await?;
+
+        Ok(result)
+    }
+
+    /// Email the patient the join link of a televisit
+    ///
+    /// With a public URL configured the email holds a signed link that records
+    /// the patient joining, valid until the room closes; otherwise it holds
+    /// the room link itself. Returns the notification, or `None` when the
+    /// patient has no email address or does not accept email.
+    pub async fn send_invitation(
+        &self,
+        session: &TelevisitSession,
+        notification_service: &NotificationService,
+        encryption_key: &EncryptionKey,
+        user_id: Uuid,
+    ) -> Result<Option<Uuid>> {
+        if !notification_service
+            .can_patient_receive_email(session.patient_id, user_id)
+            .await
+        {
+            return Ok(None);
+        }
+
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        let appointment = find_appointment(&mut tx, session.appointment_id).await?;
+        let patient = sqlx::query_as::<_, Patient>("SELECT * FROM patients WHERE id = $1")
+            .bind(session.patient_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Patient not found".to_string()))?;
+        let doctor_name: Option<String> =
+            sqlx::query_scalar("SELECT first_name || ' ' || last_name FROM users WHERE id = $1")
+                .bind(appointment.provider_id)
+                .fetch_optional(&mut *tx)
+                .await?;
+        tx.commit().await?;
+
+        let patient = patient
+            .decrypt(encryption_key)
+            .map_err(|e| AppError::Internal(format!("Failed to decrypt patient: {}", e)))?;
+        let Some(email) = patient.email else {
+            return Ok(None);
+        };
+        let patient_name = format!("{} {}", patient.first_name, patient.last_name);
+
+        let base_url = BrandingService::new(self.pool.clone())
+            .load_settings()
+            .await
+            .ok()
+            .and_then(|s| s.public_base_url);
+        let join_link = match base_url {
+            Some(base_url) => {
+                let (_, closes_at) =
+                    televisit_join_window(appointment.scheduled_start, appointment.scheduled_end);
+                NotificationService::televisit_url(
+                    encryption_key,
+                    &base_url,
+                    session.appointment_id,
+                    session.patient_id,
+                    closes_at,
+                )
+            }
+            None => session.join_url.clone(),
+        };
+
+        let notification = notification_service
+            .queue_televisit_invitation(
+                session.patient_id,
+                session.appointment_id,
+                &email,
+                &patient_name,
+                appointment.scheduled_start,
+                doctor_name.as_deref().unwrap_or("Dr."),
+                &join_link,
+                user_id,
+            )
+            .await
+            .map_err(|e| {
+                AppError::Internal(format!("Failed to queue televisit invitation: {}", e))
+            })?;
+
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        sqlx::query("UPDATE televisit_sessions SET invitation_notification_id = $2 WHERE id = $1")
+            .bind(session.id)
+            .bind(notification.id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(Some(notification.id))
+    }
+
+    /// Record the doctor joining or leaving a televisit
+    ///
+    /// Joining a cancelled, missed or completed televisit is a Conflict.
+    pub async fn record_provider_attendance(
+        &self,
+        appointment_id: Uuid,
+        attendance: TelevisitAttendance,
+        user_id: Uuid,
+    ) -> Result<TelevisitSession> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        let appointment = find_appointment(&mut tx, appointment_id).await?;
+        if attendance == TelevisitAttendance::Join && appointment.status.is_final() {
+            return Err(AppError::Conflict(
+                "The appointment was cancelled, missed or completed".to_string(),
+            ));
+        }
+
+        let session = record_attendance(
+            &mut tx,
+            appointment_id,
+            TelevisitParticipant::Provider,
+            attendance,
+        )
+        .await?
+        .ok_or_else(|| AppError::NotFound("Televisit room not found".to_string()))?;
+        tx.commit().await?;
+
+        Ok(session)
+    }
+
+    /// Record the patient joining through the invitation link and hand out
+    /// the room link, with the session id
+    ///
+    /// Outside the join window, or for a cancelled, missed or completed
+    /// televisit, answers Conflict.
+    pub async fn patient_join(
+        &self,
+        claims: &TelevisitClaims,
+    ) -> Result<(Uuid, TelevisitJoinResponse)> {
+        // The portal policies only show the patient's own appointments
+        let mut tx = begin_portal_transaction(&self.pool, claims.patient_id).await?;
+        let appointment = match find_appointment(&mut tx, claims.appointment_id).await {
+            Err(AppError::NotFound(_)) => {
+                return Err(AppError::NotFound(
+                    "This link is invalid or has expired".to_string(),
+                ))
+            }
+            result => result?,
+        };
+
+        if appointment.status.is_final() {
+            return Err(AppError::Conflict(
+                "This televisit was cancelled or has ended".to_string(),
+            ));
+        }
+        if !televisit_joinable(
+            appointment.status,
+            appointment.scheduled_start,
+            appointment.scheduled_end,
+            Utc::now(),
+        ) {
+            return Err(AppError::Conflict(
+                "This televisit cannot be joined now; the room opens shortly before the visit"
+                    .to_string(),
+            ));
+        }
+
+        let session = record_attendance(
+            &mut tx,
+            claims.appointment_id,
+            TelevisitParticipant::Patient,
+            TelevisitAttendance::Join,
+        )
+        .await?
+        .ok_or_else(|| AppError::NotFound("This link is invalid or has expired".to_string()))?;
+        tx.commit().await?;
+
+        let response = TelevisitJoinResponse {
+            join_url: session.join_url,
+            scheduled_start: appointment.scheduled_start,
+            scheduled_end: appointment.scheduled_end,
+        };
+        Ok((session.id, response))
+    }
+
+    /// Record the patient leaving through the invitation link, with the
+    /// session id
+    pub async fn patient_leave(
+        &self,
+        claims: &TelevisitClaims,
+    ) -> Result<(Uuid, TelevisitLeaveResponse)> {
+        let mut tx = begin_portal_transaction(&self.pool, claims.patient_id).await?;
+        let session = record_attendance(
+            &mut tx,
+            claims.appointment_id,
+            TelevisitParticipant::Patient,
+            TelevisitAttendance::Leave,
+        )
+        .await?
+        .ok_or_else(|| AppError::NotFound("This link is invalid or has expired".to_string()))?;
+        tx.commit().await?;
+
+        let response = TelevisitLeaveResponse {
+            left_at: session.patient_left_at.unwrap_or_else(Utc::now),
+            message: "Thank you. You have left the video visit.".to_string(),
+        };
+        Ok((session.id, response))
+    }
+}
+
+/// Appointment of a televisit, as visible to the transaction's context
+async fn find_appointment(
+    tx: &mut PgConnection,
+    appointment_id: Uuid,
+) -> Result<TelevisitAppointment> {
+    sqlx::query_as::<_, TelevisitAppointment>(
+        r#"
+        SELECT patient_id, provider_id, type, status, scheduled_start, scheduled_end
+        FROM appointments
+        WHERE id = $1
+        "#,
+    )
+    .bind(appointment_id)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Appointment not found".to_string()))
+}
+
+async fn find_session(
+    tx: &mut PgConnection,
+    appointment_id: Uuid,
+) -> Result<Option<TelevisitSession>> {
+    Ok(sqlx::query_as::<_, TelevisitSession>(&format!(
+        "SELECT {} FROM televisit_sessions WHERE appointment_id = $1",
+        SESSION_COLUMNS
+    ))
+    .bind(appointment_id)
+    .fetch_optional(&mut *tx)
+    .await?)
+}
+
+/// Keep the first join and the last leave of a participant
+async fn record_attendance(
+    tx: &mut PgConnection,
+    appointment_id: Uuid,
+    participant: TelevisitParticipant,
+    attendance: TelevisitAttendance,
+) -> Result<Option<TelevisitSession>> {
+    let assignment = match (participant, attendance) {
+        (TelevisitParticipant::Provider, TelevisitAttendance::Join) => {
+            "provider_joined_at = COALESCE(provider_joined_at, NOW())"
+        }
+        (TelevisitParticipant::Provider, TelevisitAttendance::Leave) => "provider_left_at = NOW()",
+        (TelevisitParticipant::Patient, TelevisitAttendance::Join) => {
+            "patient_joined_at = COALESCE(patient_joined_at, NOW())"
+        }
+        (TelevisitParticipant::Patient, TelevisitAttendance::Leave) => "patient_left_at = NOW()",
+    };
+
+    Ok(sqlx::query_as::<_, TelevisitSession>(&format!(
+        "UPDATE televisit_sessions SET {} WHERE appointment_id = $1 RETURNING {}",
+        assignment, SESSION_COLUMNS
+    ))
+    .bind(appointment_id)
+    .fetch_optional(&mut *tx)
+    .await?)
+}
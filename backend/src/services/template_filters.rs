@@ -0,0 +1,398 @@
+/*!
+ * Document Template Filters
+ *
+ * Curated filters and functions available to document templates, plus the
+ * variable catalog served to the template editor.
+ *
+ * All helpers are pure formatting functions: they have no access to the
+ * database, filesystem or environment, so templates stay sandboxed. Invalid
+ * input never fails a render; it is passed through unchanged (or rendered
+ * empty when undefined).
+ */
+
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use serde::Serialize;
+
+const MONTHS_IT: [&str; 12] = [
+    "gennaio", "febbraio", "marzo", "aprile", "maggio", "giugno", "luglio", "agosto",
+    "settembre", "ottobre", "novembre", "dicembre",
+];
+
+const WEEKDAYS_IT: [&str; 7] = [
+    "lunedì", "martedì", "mercoledì", "giovedì", "venerdì", "sabato", "domenica",
+];
+
+/// Parse the date formats found in template variables
+///
+/// Accepts `YYYY-MM-DD`, `DD/MM/YYYY` and RFC 3339 timestamps.
+pub fn parse_date(value: &str) -> Option<NaiveDate> {
+    let value = value.trim();
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(value, "%d/%m/%Y"))
+        .ok()
+        .or_else(|| {
+            DateTime::parse_from_rfc3339(value)
+                .ok()
+                .map(|dt| dt.date_naive())
+        })
+}
+
+/// Format a date in Italian
+///
+/// Formats: `short` (15/03/2026, default), `long` (15 marzo 2026),
+/// `full` (domenica 15 marzo 2026).
+pub fn format_date_it(date: NaiveDate, format: &str) -> String {
+    let month = MONTHS_IT[date.month0() as usize];
+    match format {
+        "long" => format!("{} {} {}", date.day(), month, date.year()),
+        "full" => format!(
+            "{} {} {} {}",
+            WEEKDAYS_IT[date.weekday().num_days_from_monday() as usize],
+            date.day(),
+            month,
+            date.year()
+        ),
+        _ => date.format("%d/%m/%Y").to_string(),
+    }
+}
+
+/// Format an amount as Italian currency (1.234,56 €)
+pub fn format_currency(amount: f64, symbol: &str) -> String {
+    let cents = (amount.abs() * 100.0).round() as u64;
+    let whole = (cents / 100).to_string();
+    let fraction = cents % 100;
+
+    let mut grouped = String::with_capacity(whole.len() + whole.len() / 3);
+    for (i, digit) in whole.chars().enumerate() {
+        if i > 0 && (whole.len() - i) % 3 == 0 {
+            grouped.push('.');
+        }
+        grouped.push(digit);
+    }
+
+    let sign = if amount < 0.0 && cents > 0 { "-" } else { "" };
+    if symbol.is_empty() {
+        format!("{}{},{:02}", sign, grouped, fraction)
+    } else {
+        format!("{}{},{:02} {}", sign, grouped, fraction, symbol)
+    }
+}
+
+/// Age in completed years on `on`
+pub fn age_in_years(date_of_birth: NaiveDate, on: NaiveDate) -> Option<u32> {
+    on.years_since(date_of_birth)
+}
+
+/// Normalize an Italian fiscal code (uppercase, no spaces)
+pub fn normalize_fiscal_code(value: &str) -> String {
+    value
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_uppercase()
+}
+
+/// Register the filters and functions on a minijinja environment
+#[cfg(feature = "pdf-export")]
+pub fn register(env: &mut minijinja::Environment<'_>) {
+    env.add_filter("date_it", filters::date_it);
+    env.add_filter("currency", filters::currency);
+    env.add_filter("age", filters::age);
+    env.add_filter("fiscal_code", filters::fiscal_code);
+    env.add_function("today", filters::today);
+}
+
+#[cfg(feature = "pdf-export")]
+mod filters {
+    use super::*;
+    use minijinja::Value;
+
+    /// Render a value as a string, or empty when undefined/none
+    fn as_text(value: &Value) -> Option<String> {
+        if value.is_undefined() || value.is_none() {
+            return None;
+        }
+        Some(match value.as_str() {
+            Some(s) => s.to_string(),
+            None => value.to_string(),
+        })
+    }
+
+    pub fn date_it(value: Value, format: Option<String>) -> String {
+        let Some(text) = as_text(&value) else {
+            return String::new();
+        };
+        match parse_date(&text) {
+            Some(date) => format_date_it(date, format.as_deref().unwrap_or("short")),
+            None => text,
+        }
+    }
+
+    pub fn currency(value: Value, symbol: Option<String>) -> String {
+        let Some(text) = as_text(&value) else {
+            return String::new();
+        };
+        let amount = f64::try_from(value)
+            .ok()
+            .or_else(|| text.trim().replace(',', ".").parse::<f64>().ok());
+        match amount {
+            Some(amount) => format_currency(amount, symbol.as_deref().unwrap_or("€")),
+            None => text,
+        }
+    }
+
+    pub fn age(value: Value, on: Option<String>) -> String {
+        let Some(text) = as_text(&value) else {
+            return String::new();
+        };
+        let on = on
+            .as_deref()
+            .and_then(parse_date)
+            .unwrap_or_else(|| Utc::now().date_naive());
+        parse_date(&text)
+            .and_then(|dob| age_in_years(dob, on))
+            .map(|years| years.to_string())
+            .unwrap_or_default()
+    }
+
+    pub fn fiscal_code(value: Value) -> String {
+        as_text(&value)
+            .map(|text| normalize_fiscal_code(&text))
+            .unwrap_or_default()
+    }
+
+    pub fn today(format: Option<String>) -> String {
+        format_date_it(Utc::now().date_naive(), format.as_deref().unwrap_or("short"))
+    }
+}
+
+// ============================================================================
+// Variable catalog
+// ============================================================================
+
+/// A variable available to document templates
+#[derive(Debug, Clone, Serialize)]
+pub struct TemplateVariableInfo {
+    pub name: &'static str,
+    pub description: &'static str,
+}
+
+/// A filter or function available to document templates
+#[derive(Debug, Clone, Serialize)]
+pub struct TemplateFilterInfo {
+    pub name: &'static str,
+    /// "filter" (`{{ value | name }}`) or "function" (`{{ name() }}`)
+    pub kind: &'static str,
+    pub usage: &'static str,
+    pub description: &'static str,
+    pub example: &'static str,
+}
+
+/// Variables and helpers available to document templates
+#[derive(Debug, Clone, Serialize)]
+pub struct TemplateCatalogResponse {
+    pub variables: Vec<TemplateVariableInfo>,
+    pub filters: Vec<TemplateFilterInfo>,
+}
+
+const VARIABLES: &[(&str, &str)] = &[
+    ("patient.id", "Patient ID"),
+    ("patient.first_name", "Patient first name"),
+    ("patient.last_name", "Patient last name"),
+    ("patient.middle_name", "Patient middle name (may be empty)"),
+    ("patient.full_name", "Patient full name"),
+    ("patient.date_of_birth", "Date of birth (YYYY-MM-DD)"),
+    ("patient.gender", "Gender"),
+    ("patient.fiscal_code", "Fiscal code"),
+    ("patient.email", "Email address"),
+    ("patient.phone", "Primary phone number"),
+    ("patient.allergies", "Active allergies (list of substance, reaction, severity)"),
+    ("provider.full_name", "Generating doctor's full name"),
+    ("provider.first_name", "Doctor's first name"),
+    ("provider.last_name", "Doctor's last name"),
+    ("provider.email", "Doctor's email address"),
+    ("provider.specialization", "Doctor's specialization"),
+    ("provider.license_number", "Doctor's license number"),
+    ("clinic.name", "Clinic name"),
+    ("clinic.address", "Street address"),
+    ("clinic.full_address", "Full address as configured"),
+    ("clinic.city", "City"),
+    ("clinic.province", "Province"),
+    ("clinic.phone", "Phone number"),
+    ("clinic.fax", "Fax number"),
+    ("clinic.email", "Email address"),
+    ("clinic.website", "Website"),
+    ("clinic.vat_number", "VAT number"),
+    ("clinic.logo", "Practice logo as a data URI (may be empty)"),
+    ("clinic.branding.primary_color", "Brand primary color"),
+    ("clinic.branding.accent_color", "Brand accent color for documents"),
+    ("document.date", "Generation date (DD/MM/YYYY)"),
+    ("certificate.content", "Certificate body text"),
+    ("certificate.prognosis_days", "Prognosis in days"),
+    ("certificate.start_date", "Certificate start date"),
+    ("certificate.end_date", "Certificate end date"),
+    ("referral.specialty", "Referral specialty"),
+    ("referral.urgency", "Referral urgency"),
+    ("referral.reason", "Reason for referral"),
+    ("referral.clinical_info", "Clinical information"),
+    ("referral.request", "Requested examination"),
+    ("referral.specialist_name", "Specialist or facility referred to"),
+    ("referral.specialist_address", "Specialist contact details (may be empty)"),
+    ("referral.response_due_date", "Response expected by (referral letters)"),
+    ("lab.tests", "List of requested lab tests"),
+    ("lab.clinical_info", "Clinical information"),
+    ("lab.urgency", "Lab request urgency"),
+    ("lab.fasting", "Whether fasting is required"),
+    ("lab.requisition_number", "Requisition number (lab order requisitions)"),
+    ("lab.barcode", "Code 39 barcode of the requisition number as a data URI"),
+    ("lab.diagnostic_question", "Diagnostic question"),
+    ("lab.special_instructions", "Special instructions for the lab"),
+    ("imaging.modality", "Imaging modality (imaging order referrals)"),
+    ("imaging.body_part", "Body part to image"),
+    ("imaging.laterality", "Side imaged (may be empty)"),
+    ("imaging.contrast", "Whether contrast medium is used"),
+    ("imaging.exam", "Full exam description"),
+    ("imaging.clinical_question", "Clinical question for the radiologist"),
+    ("imaging.urgency", "Imaging urgency"),
+    ("imaging.facility", "Imaging facility (may be empty)"),
+    ("visit.date", "Visit date"),
+    ("visit.chief_complaint", "Chief complaint"),
+    ("visit.subjective", "SOAP subjective"),
+    ("visit.objective", "SOAP objective"),
+    ("visit.assessment", "SOAP assessment"),
+    ("visit.plan", "SOAP plan"),
+    ("visit.vitals", "Vital signs"),
+    ("visit.diagnoses", "Visit diagnoses"),
+    ("prescription.medications", "Prescribed medications"),
+    ("prescription.notes", "Prescription notes"),
+];
+
+const FILTERS: &[TemplateFilterInfo] = &[
+    TemplateFilterInfo {
+        name: "date_it",
+        kind: "filter",
+        usage: "{{ value | date_it }} / {{ value | date_it('long') }} / {{ value | date_it('full') }}",
+        description: "Format a date in Italian: short (DD/MM/YYYY, default), long or full",
+        example: "{{ patient.date_of_birth | date_it('long') }} → 15 marzo 1980",
+    },
+    TemplateFilterInfo {
+        name: "currency",
+        kind: "filter",
+        usage: "{{ value | currency }} / {{ value | currency('EUR') }}",
+        description: "Format an amount with Italian separators and a currency symbol (default €)",
+        example: "{{ 1234.5 | currency }} → 1.234,50 €",
+    },
+    TemplateFilterInfo {
+        name: "age",
+        kind: "filter",
+        usage: "{{ date_of_birth | age }} / {{ date_of_birth | age(reference_date) }}",
+        description: "Age in completed years, today or on a reference date",
+        example: "{{ patient.date_of_birth | age }} → 45",
+    },
+    TemplateFilterInfo {
+        name: "fiscal_code",
+        kind: "filter",
+        usage: "{{ value | fiscal_code }}",
+        description: "Uppercase a fiscal code and remove spaces",
+        example: "{{ 'rssmra80c15h501 u' | fiscal_code }} → RSSMRA80C15H501U",
+    },
+    TemplateFilterInfo {
+        name: "today",
+        kind: "function",
+        usage: "{{ today() }} / {{ today('long') }}",
+        description: "Current date in Italian (same formats as date_it)",
+        example: "{{ today('long') }} → 16 ottobre 2026",
+    },
+];
+
+/// Variables and helpers documented for the template editor
+pub fn catalog() -> TemplateCatalogResponse {
+    TemplateCatalogResponse {
+        variables: VARIABLES
+            .iter()
+            .map(|(name, description)| TemplateVariableInfo { name, description })
+            .collect(),
+        filters: FILTERS.to_vec(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_parse_date_formats() {
+        assert_eq!(parse_date("2026-03-15"), Some(date(2026, 3, 15)));
+        assert_eq!(parse_date("15/03/2026"), Some(date(2026, 3, 15)));
+        assert_eq!(parse_date("2026-03-15T10:30:00Z"), Some(date(2026, 3, 15)));
+        assert_eq!(parse_date("not a date"), None);
+    }
+
+    #[test]
+    fn test_format_date_it() {
+        let d = date(2026, 3, 15);
+        assert_eq!(format_date_it(d, "short"), "15/03/2026");
+        assert_eq!(format_date_it(d, "long"), "15 marzo 2026");
+        assert_eq!(format_date_it(d, "full"), "domenica 15 marzo 2026");
+        assert_eq!(format_date_it(d, "unknown"), "15/03/2026");
+    }
+
+    #[test]
+    fn test_format_currency() {
+        assert_eq!(format_currency(0.0, "€"), "0,00 €");
+        assert_eq!(format_currency(12.5, "€"), "12,50 €");
+        assert_eq!(format_currency(1234.567, "€"), "1.234,57 €");
+        assert_eq!(format_currency(1_234_567.0, "EUR"), "1.234.567,00 EUR");
+        assert_eq!(format_currency(-45.1, "€"), "-45,10 €");
+        assert_eq!(format_currency(99.0, ""), "99,00");
+    }
+
+    #[test]
+    fn test_age_in_years() {
+        let dob = date(1980, 3, 15);
+        assert_eq!(age_in_years(dob, date(2026, 3, 14)), Some(45));
+        assert_eq!(age_in_years(dob, date(2026, 3, 15)), Some(46));
+        assert_eq!(age_in_years(dob, date(1979, 1, 1)), None);
+    }
+
+    #[test]
+    fn test_normalize_fiscal_code() {
+        assert_eq!(normalize_fiscal_code("rssmra80c15h501 u"), "RSSMRA80C15H501U");
+    }
+
+    #[test]
+    fn test_catalog_lists_every_filter() {
+        let catalog = catalog();
+        let names: Vec<&str> = catalog.filters.iter().map(|f| f.name).collect();
+        assert_eq!(names, vec!["date_it", "currency", "age", "fiscal_code", "today"]);
+        assert!(catalog.variables.iter().any(|v| v.name == "patient.date_of_birth"));
+    }
+
+    #[cfg(feature = "pdf-export")]
+    #[test]
+    fn test_filters_render_in_template() {
+        let mut env = minijinja::Environment::new();
+        register(&mut env);
+        env.add_template(
+            "t",
+            "{{ dob | date_it('long') }}|{{ amount | currency }}|{{ dob | age('2026-03-15') }}|{{ cf | fiscal_code }}|{{ missing | date_it }}",
+        )
+        .unwrap();
+
+        let rendered = env
+            .get_template("t")
+            .unwrap()
+            .render(minijinja::context! {
+                dob => "1980-03-15",
+                amount => 1234.5,
+                cf => "rssmra80c15h501u",
+            })
+            .unwrap();
+
+        assert_eq!(rendered, "15 marzo 1980|1.234,50 €|46|RSSMRA80C15H501U|");
+    }
+}
@@ -0,0 +1,186 @@
+/*!
+ * Transcription Engines
+ *
+ * Speech-to-text behind the `TranscriptionEngine` trait, selected with
+ * TRANSCRIPTION_PROVIDER:
+ * - `whisper`: runs a local whisper.cpp binary on WAV and MP3 memos, so the
+ *   audio never leaves the server
+ * - `http`: POSTs the audio to an external service that answers
+ *   `{"text": "..."}`, for any recorded format
+ *
+ * Engines only turn audio into text; scheduling, storage and the proposal
+ * for the visit note are in `visit_transcription_service`.
+ */
+
+use crate::config::{TranscriptionConfig, TranscriptionProvider};
+use anyhow::{Context, Result};
+use futures::future::BoxFuture;
+use serde::Deserialize;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::time::timeout;
+
+/// Transcribes a dictated audio memo
+pub trait TranscriptionEngine: Send + Sync {
+    /// Engine name, for logs
+    fn name(&self) -> &'static str;
+
+    /// Whether the engine can read audio of this type
+    fn supports(&self, mime_type: &str) -> bool;
+
+    /// Transcribe an audio file
+    fn transcribe<'a>(
+        &'a self,
+        content: &'a [u8],
+        mime_type: &'a str,
+    ) -> BoxFuture<'a, Result<String>>;
+}
+
+/// Build the engine configured with TRANSCRIPTION_PROVIDER
+pub fn build_transcription_engine(
+    config: &TranscriptionConfig,
+) -> Result<Arc<dyn TranscriptionEngine>> {
+    Ok(match &config.provider {
+        TranscriptionProvider::Whisper(model_path) => Arc::new(WhisperTranscriptionEngine {
+            binary: config.whisper_path.clone(),
+            model_path: model_path.clone(),
+            language: config.language.clone(),
+            timeout: config.timeout,
+        }),
+        TranscriptionProvider::Http(endpoint) => Arc::new(HttpTranscriptionEngine {
+            client: reqwest::Client::builder()
+                .timeout(config.timeout)
+                .build()
+                .context("Failed to build transcription HTTP client")?,
+            endpoint: endpoint.clone(),
+            language: config.language.clone(),
+            auth_token: config.auth_token().map(str::to_string),
+        }),
+    })
+}
+
+/// Local whisper.cpp binary, fed through stdin (`-f -`) so no plaintext
+/// copy of the memo is written to disk
+pub struct WhisperTranscriptionEngine {
+    binary: String,
+    model_path: String,
+    language: String,
+    timeout: Duration,
+}
+
+impl TranscriptionEngine for WhisperTranscriptionEngine {
+    fn name(&self) -> &'static str {
+        "whisper"
+    }
+
+    fn supports(&self, mime_type: &str) -> bool {
+        matches!(mime_type, "audio/wav" | "audio/mpeg")
+    }
+
+    fn transcribe<'a>(
+        &'a self,
+        content: &'a [u8],
+        _mime_type: &'a str,
+    ) -> BoxFuture<'a, Result<String>> {
+        Box::pin(async move {
+            let mut child = Command::new(&self.binary)
+                .args([
+                    "-m",
+                    &self.model_path,
+                    "-l",
+                    &self.language,
+                    // Plain text only: no timestamps, no progress output
+                    "-nt",
+                    "-np",
+                    "-f",
+                    "-",
+                ])
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .kill_on_drop(true)
+                .spawn()
+                .with_context(|| format!("Failed to start {}", self.binary))?;
+
+            let mut stdin = child.stdin.take().context("Whisper stdin unavailable")?;
+            let run = async {
+                stdin.write_all(content).await?;
+                drop(stdin);
+                child.wait_with_output().await
+            };
+
+            let output = timeout(self.timeout, run)
+                .await
+                .context("Whisper timed out")?
+                .context("Whisper failed")?;
+
+            if !output.status.success() {
+                anyhow::bail!(
+                    "Whisper exited with {}: {}",
+                    output.status,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                );
+            }
+
+            Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+        })
+    }
+}
+
+/// External speech-to-text service
+pub struct HttpTranscriptionEngine {
+    client: reqwest::Client,
+    endpoint: String,
+    language: String,
+    auth_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct HttpTranscriptionResponse {
+    text: String,
+}
+
+impl TranscriptionEngine for HttpTranscriptionEngine {
+    fn name(&self) -> &'static str {
+        "http"
+    }
+
+    fn supports(&self, mime_type: &str) -> bool {
+        mime_type.starts_with("audio/")
+    }
+
+    fn transcribe<'a>(
+        &'a self,
+        content: &'a [u8],
+        mime_type: &'a str,
+    ) -> BoxFuture<'a, Result<String>> {
+        Box::pin(async move {
+            let mut request = self
+                .client
+                .post(&self.endpoint)
+                .query(&[("language", self.language.as_str())])
+                .header(reqwest::header::CONTENT_TYPE, mime_type)
+                .body(content.to_vec());
+            if let Some(token) = &self.auth_token {
+                request = request.bearer_auth(token);
+            }
+
+            let response = request
+                .send()
+                .await
+                .context("Transcription service unreachable")?
+                .error_for_status()
+                .context("Transcription service rejected the audio")?;
+
+            let body: HttpTranscriptionResponse = response
+                .json()
+                .await
+                .context("Invalid transcription service response")?;
+
+            Ok(body.text)
+        })
+    }
+}
@@ -0,0 +1,270 @@
+/*!
+ * Recycle Bin Service
+ *
+ * Lists and restores deleted patients, draft visits and prescriptions.
+ * Records in the bin are hidden by RLS unless the transaction asks for them
+ * (`include_trashed_records`); restoring names the record with
+ * `target_trash_record`, so the usual update policies still decide who may
+ * bring it back. Purging is left to the RECYCLE_BIN retention rule.
+ */
+
+use crate::{
+    db::rls::{begin_with_rls, include_trashed_records, target_trash_record},
+    models::{ListTrashQuery, RetentionEntityType, TrashEntityType, TrashItem, TrashListResponse},
+    utils::{encryption::EncryptionKey, AppError, Result},
+};
+use chrono::{DateTime, Duration, Utc};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+const PATIENT_TRASH_SELECT: &str = r#"
+    SELECT 'PATIENT'::TEXT AS entity_type, p.id, p.id AS patient_id,
+           p.first_name AS patient_first_name, p.last_name AS patient_last_name,
+           NULL::TEXT AS summary, NULL::TEXT AS encrypted_summary,
+           p.deleted_at, p.deleted_by
+    FROM patients p
+    WHERE p.deleted_at IS NOT NULL
+"#;
+
+const VISIT_TRASH_SELECT: &str = r#"
+    SELECT 'VISIT'::TEXT, v.id, v.patient_id,
+           p.first_name, p.last_name,
+           v.visit_type::TEXT || ' ' || v.visit_date::TEXT, NULL::TEXT,
+           v.deleted_at, v.deleted_by
+    FROM visits v
+    LEFT JOIN patients p ON p.id = v.patient_id
+    WHERE v.deleted_at IS NOT NULL
+"#;
+
+const PRESCRIPTION_TRASH_SELECT: &str = r#"
+    SELECT 'PRESCRIPTION'::TEXT, r.id, r.patient_id,
+           p.first_name, p.last_name,
+           NULL::TEXT, r.medication_name,
+           r.deleted_at, r.deleted_by
+    FROM prescriptions r
+    LEFT JOIN patients p ON p.id = r.patient_id
+    WHERE r.deleted_at IS NOT NULL
+"#;
+
+/// Row of the recycle bin listing, names still encrypted
+#[derive(Debug, FromRow)]
+struct TrashRow {
+    entity_type: String,
+    id: Uuid,
+    patient_id: Uuid,
+    patient_first_name: Option<String>,
+    patient_last_name: Option<String>,
+    summary: Option<String>,
+    encrypted_summary: Option<String>,
+    deleted_at: DateTime<Utc>,
+    deleted_by: Option<Uuid>,
+    deleted_by_name: Option<String>,
+}
+
+/// Recycle bin service
+pub struct TrashService {
+    pool: PgPool,
+    encryption_key: EncryptionKey,
+}
+
+impl TrashService {
+    /// Create a new recycle bin service
+    pub fn new(pool: PgPool, encryption_key: EncryptionKey) -> Self {
+        Self {
+            pool,
+            encryption_key,
+        }
+    }
+
+    /// Records of the given types in the recycle bin, most recently
+    /// deleted first
+    pub async fn list(
+        &self,
+        entity_types: &[TrashEntityType],
+        query: &ListTrashQuery,
+        user_id: Uuid,
+    ) -> Result<TrashListResponse> {
+        let limit = query.limit();
+        let offset = query.offset();
+        if entity_types.is_empty() {
+            return Ok(TrashListResponse {
+                items: Vec::new(),
+                total: 0,
+                limit,
+                offset,
+            });
+        }
+
+        let union = entity_types
+            .iter()
+            .map(|t| match t {
+                TrashEntityType::Patient => PATIENT_TRASH_SELECT,
+                TrashEntityType::Visit => VISIT_TRASH_SELECT,
+                TrashEntityType::Prescription => PRESCRIPTION_TRASH_SELECT,
+            })
+            .collect::<Vec<_>>()
+            .join(" UNION ALL ");
+
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        include_trashed_records(&mut tx).await?;
+
+        let total: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM ({}) t", union))
+            .fetch_one(&mut *tx)
+            .await?;
+
+        let rows = sqlx::query_as::<_, TrashRow>(&format!(
+            r#"
+            SELECT t.*, u.first_name || ' ' || u.last_name AS deleted_by_name
+            FROM ({}) t
+            LEFT JOIN users u ON u.id = t.deleted_by
+            ORDER BY t.deleted_at DESC, t.id
+            LIMIT $1 OFFSET $2
+            "#,
+            union
+        ))
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let rule: Option<(i32, bool, bool)> = sqlx::query_as(
+            "SELECT retention_days, is_enabled, dry_run FROM retention_rules WHERE entity_type = $1",
+        )
+        .bind(RetentionEntityType::RecycleBin.as_str())
+        .fetch_optional(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        let retention = rule
+            .filter(|(_, is_enabled, dry_run)| *is_enabled && !*dry_run)
+            .map(|(days, _, _)| Duration::days(days as i64));
+
+        let items = rows
+            .into_iter()
+            .map(|row| self.to_item(row, retention))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(TrashListResponse {
+            items,
+            total,
+            limit,
+            offset,
+        })
+    }
+
+    /// Take a record out of the recycle bin, returning its patient
+    ///
+    /// Visits and prescriptions of a patient still in the bin cannot be
+    /// restored before the patient. Fails with Forbidden when RLS does not
+    /// let the user update the record (e.g. another doctor's visit).
+    pub async fn restore(
+        &self,
+        entity_type: TrashEntityType,
+        id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Uuid> {
+        let (table, patient_column) = match entity_type {
+            TrashEntityType::Patient => ("patients", "id"),
+            TrashEntityType::Visit => ("visits", "patient_id"),
+            TrashEntityType::Prescription => ("prescriptions", "patient_id"),
+        };
+
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        include_trashed_records(&mut tx).await?;
+        target_trash_record(&mut tx, id).await?;
+
+        let record: Option<(Uuid, Option<DateTime<Utc>>)> = sqlx::query_as(&format!(
+            "SELECT {}, deleted_at FROM {} WHERE id = $1",
+            patient_column, table
+        ))
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let (patient_id, deleted_at) = record.ok_or_else(|| {
+            AppError::NotFound(format!("{} {} not found", entity_type.as_str(), id))
+        })?;
+        if deleted_at.is_none() {
+            return Err(AppError::Conflict(format!(
+                "{} {} is not in the recycle bin",
+                entity_type.as_str(),
+                id
+            )));
+        }
+
+        if entity_type != TrashEntityType::Patient {
+            let patient_deleted: Option<bool> =
+                sqlx::query_scalar("SELECT deleted_at IS NOT NULL FROM patients WHERE id = $1")
+                    .bind(patient_id)
+                    .fetch_optional(&mut *tx)
+                    .await?;
+            if patient_deleted == Some(true) {
+                return Err(AppError::Conflict(format!(
+                    "Patient {} is in the recycle bin; restore the patient first",
+                    patient_id
+                )));
+            }
+        }
+
+        let restored = sqlx::query(&format!(
+            "UPDATE {} SET deleted_at = NULL, deleted_by = NULL WHERE id = $1 AND deleted_at IS NOT NULL",
+            table
+        ))
+        .bind(id)
+        .execute(&mut *tx)
+        .await;
+        let restored = match restored {
+            Ok(result) => result.rows_affected(),
+            Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+                return Err(AppError::Conflict(format!(
+                    "{} {} conflicts with a record created since it was deleted",
+                    entity_type.as_str(),
+                    id
+                )))
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if restored == 0 {
+            return Err(AppError::Forbidden(format!(
+                "User may not restore {} {}",
+                entity_type.as_str(),
+                id
+            )));
+        }
+
+        tx.commit().await?;
+        Ok(patient_id)
+    }
+
+    fn to_item(&self, row: TrashRow, retention: Option<Duration>) -> Result<TrashItem> {
+        let entity_type = TrashEntityType::from_str(&row.entity_type).ok_or_else(|| {
+            AppError::Internal(format!("Unknown trash entity type: {}", row.entity_type))
+        })?;
+
+        let decrypt = |value: &str| {
+            self.encryption_key
+                .decrypt(value)
+                .map_err(|e| AppError::Internal(format!("Failed to decrypt trash item: {}", e)))
+        };
+
+        let patient_name = match (&row.patient_first_name, &row.patient_last_name) {
+            (Some(first), Some(last)) => Some(format!("{} {}", decrypt(first)?, decrypt(last)?)),
+            _ => None,
+        };
+        let summary = match &row.encrypted_summary {
+            Some(encrypted) => Some(decrypt(encrypted)?),
+            None => row.summary,
+        };
+
+        Ok(TrashItem {
+            entity_type,
+            id: row.id,
+            patient_id: row.patient_id,
+            patient_name,
+            summary,
+            deleted_at: row.deleted_at,
+            deleted_by: row.deleted_by,
+            deleted_by_name: row.deleted_by_name,
+            purge_after: retention.map(|retention| row.deleted_at + retention),
+        })
+    }
+}
@@ -0,0 +1,133 @@
+/*!
+ * Trusted Device Service
+ *
+ * Stores and checks devices that may skip the MFA code step at login
+ * ("remember this device for 30 days"). Token signing lives in JwtService;
+ * this service only manages `trusted_devices` rows.
+ */
+
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::trusted_device::TrustedDevice;
+use crate::utils::Result;
+
+/// Columns selected for TrustedDevice
+const DEVICE_COLUMNS: &str = r#"
+    id, user_id, device_name, host(ip_address) AS ip_address,
+    created_at, last_used_at, expires_at, revoked_at
+"#;
+
+/// Trusted device service
+pub struct TrustedDeviceService {
+    pool: PgPool,
+}
+
+impl TrustedDeviceService {
+    /// Create a new trusted device service
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Trust a device for the user until `expires_at`
+    pub async fn create(
+        &self,
+        user_id: Uuid,
+        device_name: Option<String>,
+        ip_address: Option<&str>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<TrustedDevice> {
+        let ip_address = ip_address.filter(|ip| ip.parse::<std::net::IpAddr>().is_ok());
+
+        let device = sqlx::query_as::<_, TrustedDevice>(&format!(
+            r#"
+            INSERT INTO trusted_devices (user_id, device_name, ip_address, expires_at)
+            VALUES ($1, $2, $3::inet, $4)
+            RETURNING {}
+            "#,
+            DEVICE_COLUMNS
+        ))
+        .bind(user_id)
+        .bind(device_name)
+        .bind(ip_address)
+        .bind(expires_at)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(device)
+    }
+
+    /// Find a device that is trusted for this user right now and mark it used
+    pub async fn use_active(&self, user_id: Uuid, device_id: Uuid) -> Result<Option<TrustedDevice>> {
+        let device = sqlx::query_as::<_, TrustedDevice>(&format!(
+            r#"
+            UPDATE trusted_devices
+            SET last_used_at = NOW()
+            WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL AND expires_at > NOW()
+            RETURNING {}
+            "#,
+            DEVICE_COLUMNS
+        ))
+        .bind(device_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(device)
+    }
+
+    /// Active devices for a user, most recently used first
+    pub async fn list_active(&self, user_id: Uuid) -> Result<Vec<TrustedDevice>> {
+        let devices = sqlx::query_as::<_, TrustedDevice>(&format!(
+            r#"
+            SELECT {}
+            FROM trusted_devices
+            WHERE user_id = $1 AND revoked_at IS NULL AND expires_at > NOW()
+            ORDER BY COALESCE(last_used_at, created_at) DESC
+            "#,
+            DEVICE_COLUMNS
+        ))
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(devices)
+    }
+
+    /// Revoke one of the user's devices
+    ///
+    /// Returns false if the device does not exist, belongs to another user or
+    /// is already revoked.
+    pub async fn revoke(&self, user_id: Uuid, device_id: Uuid) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE trusted_devices
+            SET revoked_at = NOW()
+            WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL
+            "#,
+        )
+        .bind(device_id)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Revoke all of the user's devices, returning how many were revoked
+    pub async fn revoke_all(&self, user_id: Uuid) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            UPDATE trusted_devices
+            SET revoked_at = NOW()
+            WHERE user_id = $1 AND revoked_at IS NULL AND expires_at > NOW()
+            "#,
+        )
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
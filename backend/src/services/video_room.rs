@@ -0,0 +1,184 @@
+/*!
+ * Video Room Providers
+ *
+ * Video rooms for televisits behind the `VideoRoomProvider` trait, selected
+ * with VIDEO_PROVIDER:
+ * - `jitsi`: rooms on a Jitsi Meet server, which opens a room the first time
+ *   someone joins it; the room name is random so it cannot be guessed
+ * - `http`: POSTs the appointment to an external service that answers
+ *   `{"room_name": "...", "join_url": "..."}`
+ *
+ * Providers only create rooms; sessions, join links and attendance are in
+ * `televisit_service`.
+ */
+
+use crate::config::{VideoConfig, VideoProvider};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Appointment a room is created for
+#[derive(Debug, Clone, Serialize)]
+pub struct VideoRoomRequest {
+    pub appointment_id: Uuid,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+}
+
+/// A provisioned room
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct VideoRoom {
+    pub room_name: String,
+    /// Link that opens the room, for the patient and the doctor
+    pub join_url: String,
+}
+
+/// Creates video rooms for televisits
+pub trait VideoRoomProvider: Send + Sync {
+    /// Provider name, stored with each session
+    fn name(&self) -> &'static str;
+
+    /// Create a room for an appointment
+    fn create_room<'a>(&'a self, request: &'a VideoRoomRequest)
+        -> BoxFuture<'a, Result<VideoRoom>>;
+}
+
+/// Build the provider configured with VIDEO_PROVIDER
+pub fn build_video_room_provider(config: &VideoConfig) -> Result<Arc<dyn VideoRoomProvider>> {
+    Ok(match &config.provider {
+        VideoProvider::Jitsi(base_url) => Arc::new(JitsiVideoRoomProvider {
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }),
+        VideoProvider::Http(endpoint) => Arc::new(HttpVideoRoomProvider {
+            client: reqwest::Client::builder()
+                .timeout(config.timeout)
+                .build()
+                .context("Failed to build video room HTTP client")?,
+            endpoint: endpoint.clone(),
+            auth_token: config.auth_token().map(str::to_string),
+        }),
+    })
+}
+
+/// Jitsi Meet server
+pub struct JitsiVideoRoomProvider {
+    base_url: String,
+}
+
+impl JitsiVideoRoomProvider {
+    /// Random room name (16 random bytes, hex); it carries no patient data
+    fn room_name() -> String {
+        let mut bytes = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        format!("docpat-{}", hex::encode(bytes))
+    }
+}
+
+impl VideoRoomProvider for JitsiVideoRoomProvider {
+    fn name(&self) -> &'static str {
+        "jitsi"
+    }
+
+    fn create_room<'a>(
+        &'a self,
+        _request: &'a VideoRoomRequest,
+    ) -> BoxFuture<'a, Result<VideoRoom>> {
+        Box::pin(async move {
+            let room_name = Self::room_name();
+            Ok(VideoRoom {
+                join_url: format!("{}/{}", self.base_url, room_name),
+                room_name,
+            })
+        })
+    }
+}
+
+/// External video service
+pub struct HttpVideoRoomProvider {
+    client: reqwest::Client,
+    endpoint: String,
+    auth_token: Option<String>,
+}
+
+impl VideoRoomProvider for HttpVideoRoomProvider {
+    fn name(&self) -> &'static str {
+        "http"
+    }
+
+    fn create_room<'a>(
+        &'a self,
+        request: &'a VideoRoomRequest,
+    ) -> BoxFuture<'a, Result<VideoRoom>> {
+        Box::pin(async move {
+            let mut http_request = self.client.post(&self.endpoint).json(request);
+            if let Some(token) = &self.auth_token {
+                http_request = http_request.bearer_auth(token);
+            }
+
+            let room: VideoRoom = http_request
+                .send()
+                .await
+                .context("Video service unreachable")?
+                .error_for_status()
+                .context("Video service rejected the room request")?
+                .json()
+                .await
+                .context("Invalid video service response")?;
+
+            if !(room.join_url.starts_with("https://") || room.join_url.starts_with("http://")) {
+                anyhow::bail!("Video service returned an invalid join URL");
+            }
+
+            Ok(room)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request() -> VideoRoomRequest {
+        let starts_at = Utc::now();
+        VideoRoomRequest {
+            appointment_id: Uuid::new_v4(),
+            starts_at,
+            ends_at: starts_at + chrono::Duration::minutes(30),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_jitsi_rooms_are_unguessable() {
+        let provider = JitsiVideoRoomProvider {
+            base_url: "https://meet.example.com".to_string(),
+        };
+
+        let first = provider.create_room(&request()).await.unwrap();
+        let second = provider.create_room(&request()).await.unwrap();
+
+        assert!(first.room_name.starts_with("docpat-"));
+        assert_eq!(first.room_name.len(), "docpat-".len() + 32);
+        assert_eq!(
+            first.join_url,
+            format!("https://meet.example.com/{}", first.room_name)
+        );
+        assert_ne!(first.room_name, second.room_name);
+    }
+
+    #[tokio::test]
+    async fn test_build_jitsi_provider_trims_base_url() {
+        let config = VideoConfig::test_config(VideoProvider::Jitsi(
+            "https://meet.example.com/".to_string(),
+        ));
+
+        let provider = build_video_room_provider(&config).unwrap();
+        assert_eq!(provider.name(), "jitsi");
+
+        let room = provider.create_room(&request()).await.unwrap();
+        assert!(room.join_url.starts_with("https://meet.example.com/docpat-"));
+    }
+}
@@ -0,0 +1,193 @@
+/*!
+ * Visit Addendum Service
+ *
+ * Appends signed addenda to signed or locked visits, under RLS (doctors
+ * write, doctors and nurses read). The visit note is left untouched and
+ * addenda cannot be changed afterwards. Each addendum is signed when it is
+ * created, with a hash chained to the visit's signature; reads report
+ * whether that signature still holds. The reason and the content are
+ * encrypted.
+ */
+
+use crate::{
+    db::rls::begin_with_rls,
+    models::{
+        visit_addendum::addendum_signature_hash, CreateVisitAddendumRequest, VisitAddendum,
+        VisitAddendumResponse,
+    },
+    utils::{encryption::EncryptionKey, AppError, Result},
+};
+use chrono::{NaiveDate, SubsecRound, Utc};
+use sqlx::{PgConnection, PgPool};
+use tracing::info;
+use uuid::Uuid;
+
+const ADDENDUM_COLUMNS: &str = r#"
+    a.id, a.visit_id, a.visit_date, a.patient_id, a.addendum_type, a.reason, a.content,
+    a.signed_by, u.first_name || ' ' || u.last_name AS signed_by_name,
+    a.signed_at, a.signature_hash, a.created_at
+"#;
+
+/// Visit addendum service
+pub struct VisitAddendumService {
+    pool: PgPool,
+    encryption_key: EncryptionKey,
+}
+
+/// Visit an addendum is attached to
+struct SignedVisit {
+    patient_id: Uuid,
+    visit_date: NaiveDate,
+    status: String,
+    signature_hash: Option<String>,
+}
+
+impl VisitAddendumService {
+    /// Create a new visit addendum service
+    pub fn new(pool: PgPool, encryption_key: EncryptionKey) -> Self {
+        Self {
+            pool,
+            encryption_key,
+        }
+    }
+
+    /// Addenda of a visit, oldest first
+    pub async fn list_addenda(
+        &self,
+        visit_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Vec<VisitAddendumResponse>> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        let visit = self.find_visit(&mut tx, visit_id).await?;
+
+        let addenda = sqlx::query_as::<_, VisitAddendum>(&format!(
+            r#"
+            SELECT {}
+            FROM visit_addenda a
+            LEFT JOIN users u ON u.id = a.signed_by
+            WHERE a.visit_id = $1
+            ORDER BY a.signed_at
+            "#,
+            ADDENDUM_COLUMNS
+        ))
+        .bind(visit_id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        let visit_signature = visit.signature_hash.unwrap_or_default();
+        addenda
+            .iter()
+            .map(|a| self.decrypt_addendum(a, &visit_signature))
+            .collect()
+    }
+
+    /// Sign and append an addendum to a signed or locked visit
+    pub async fn create_addendum(
+        &self,
+        visit_id: Uuid,
+        request: &CreateVisitAddendumRequest,
+        user_id: Uuid,
+    ) -> Result<VisitAddendumResponse> {
+        let reason = request
+            .reason
+            .as_deref()
+            .map(str::trim)
+            .filter(|r| !r.is_empty());
+        let encrypted_reason = reason.map(|r| self.encrypt(r)).transpose()?;
+        let encrypted_content = self.encrypt(&request.content)?;
+
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        let visit = self.find_visit(&mut tx, visit_id).await?;
+        if visit.status != "SIGNED" && visit.status != "LOCKED" {
+            return Err(AppError::Conflict(format!(
+                "Visit {} is {}; edit the note instead of adding an addendum",
+                visit_id, visit.status
+            )));
+        }
+
+        // Postgres keeps microseconds: sign the same instant that is stored
+        let signed_at = Utc::now().trunc_subsecs(6);
+        let signature_hash = addendum_signature_hash(
+            visit.signature_hash.as_deref().unwrap_or(""),
+            request.addendum_type.as_str(),
+            reason,
+            &request.content,
+            user_id,
+            signed_at,
+        );
+
+        let addendum = sqlx::query_as::<_, VisitAddendum>(&format!(
+            r#"
+            WITH a AS (
+                INSERT INTO visit_addenda (
+                    visit_id, visit_date, patient_id, addendum_type, reason, content,
+                    signed_by, signed_at, signature_hash
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                RETURNING *
+            )
+            SELECT {}
+            FROM a
+            LEFT JOIN users u ON u.id = a.signed_by
+            "#,
+            ADDENDUM_COLUMNS
+        ))
+        .bind(visit_id)
+        .bind(visit.visit_date)
+        .bind(visit.patient_id)
+        .bind(request.addendum_type.as_str())
+        .bind(&encrypted_reason)
+        .bind(&encrypted_content)
+        .bind(user_id)
+        .bind(signed_at)
+        .bind(&signature_hash)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        info!(
+            "{} {} added to visit {} by {}",
+            addendum.addendum_type, addendum.id, visit_id, user_id
+        );
+
+        self.decrypt_addendum(&addendum, visit.signature_hash.as_deref().unwrap_or(""))
+    }
+
+    async fn find_visit(&self, tx: &mut PgConnection, visit_id: Uuid) -> Result<SignedVisit> {
+        let row: Option<(Uuid, NaiveDate, String, Option<String>)> = sqlx::query_as(
+            "SELECT patient_id, visit_date, status, signature_hash FROM visits WHERE id = $1",
+        )
+        .bind(visit_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let (patient_id, visit_date, status, signature_hash) =
+            row.ok_or_else(|| AppError::NotFound(format!("Visit {} not found", visit_id)))?;
+
+        Ok(SignedVisit {
+            patient_id,
+            visit_date,
+            status,
+            signature_hash,
+        })
+    }
+
+    fn decrypt_addendum(
+        &self,
+        addendum: &VisitAddendum,
+        visit_signature_hash: &str,
+    ) -> Result<VisitAddendumResponse> {
+        addendum
+            .decrypt(&self.encryption_key, visit_signature_hash)
+            .map_err(|e| AppError::Internal(format!("Failed to decrypt addendum: {}", e)))
+    }
+
+    fn encrypt(&self, value: &str) -> Result<String> {
+        self.encryption_key
+            .encrypt(value)
+            .map_err(|e| AppError::Internal(format!("Failed to encrypt addendum: {}", e)))
+    }
+}
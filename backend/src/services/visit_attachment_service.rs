@@ -0,0 +1,476 @@
+/*!
+ * Visit Attachment Service
+ *
+ * Clinical photos, audio memos and files attached to a visit. The content
+ * is stored through `FileUploadService`, encrypted at rest; the metadata
+ * lives in `visit_attachments`, under RLS (doctors and nurses read and add,
+ * doctors delete).
+ *
+ * Attachments change only while the visit is a draft; once it is signed
+ * they are part of the record. Deleting one only marks it deleted and keeps
+ * the encrypted file.
+ *
+ * Audio memos are queued for transcription on upload (see
+ * `visit_transcription_service`); their transcript and proposed note text
+ * are decrypted into the response.
+ */
+
+use crate::{
+    db::rls::begin_with_rls,
+    models::{
+        uploaded_file::FilePurpose, CreateVisitAttachmentRequest, TranscriptProposal,
+        TranscriptionStatus, VisitAttachment, VisitAttachmentKind, VisitAttachmentResponse,
+    },
+    services::FileUploadService,
+    utils::{encryption::EncryptionKey, AppError, Result},
+};
+use chrono::NaiveDate;
+use sqlx::{PgConnection, PgPool};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+const ATTACHMENT_SELECT: &str = r#"
+    SELECT a.id, a.visit_id, a.visit_date, a.patient_id, a.file_id, a.kind, a.caption,
+           a.body_site, a.transcription_status, a.transcript, a.transcript_proposal,
+           a.created_by, a.created_at,
+           f.original_filename, f.mime_type, f.file_size_bytes
+    FROM visit_attachments a
+    JOIN uploaded_files f ON f.id = a.file_id
+    WHERE a.deleted_at IS NULL
+"#;
+
+/// Visit an attachment belongs to
+struct AttachedVisit {
+    patient_id: Uuid,
+    visit_date: NaiveDate,
+    status: String,
+}
+
+/// Visit attachment service
+pub struct VisitAttachmentService {
+    pool: PgPool,
+    encryption_key: EncryptionKey,
+}
+
+impl VisitAttachmentService {
+    /// Create a new visit attachment service
+    pub fn new(pool: PgPool, encryption_key: EncryptionKey) -> Self {
+        Self {
+            pool,
+            encryption_key,
+        }
+    }
+
+    /// Attach a photo, audio memo or file to a draft visit
+    ///
+    /// Fails with Validation if the file is not an allowed type (by magic
+    /// bytes) and with Conflict if the visit is no longer a draft.
+    pub async fn create_attachment(
+        &self,
+        visit_id: Uuid,
+        content: &[u8],
+        original_filename: &str,
+        req: &CreateVisitAttachmentRequest,
+        user_id: Uuid,
+    ) -> Result<VisitAttachmentResponse> {
+        let validation = FileUploadService::validate_file(
+            content,
+            original_filename,
+            FilePurpose::VisitAttachment,
+        );
+        if !validation.is_valid {
+            return Err(AppError::Validation(
+                validation
+                    .error_message
+                    .unwrap_or_else(|| "Invalid file".to_string()),
+            ));
+        }
+        let kind = VisitAttachmentKind::from_mime_type(
+            validation.detected_mime_type.as_deref().unwrap_or_default(),
+        );
+        // Only audio memos are transcribed
+        let transcription_status =
+            (kind == VisitAttachmentKind::Audio).then_some(TranscriptionStatus::Pending.as_str());
+
+        let caption = req
+            .caption
+            .as_deref()
+            .map(str::trim)
+            .filter(|c| !c.is_empty());
+        let encrypted_caption = caption.map(|c| self.encrypt(c)).transpose()?;
+        let body_site = req
+            .body_site
+            .as_deref()
+            .map(str::trim)
+            .filter(|s| !s.is_empty());
+
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        let visit = self.find_draft_visit(&mut tx, visit_id).await?;
+
+        let file = FileUploadService::upload_encrypted_file(
+            &self.pool,
+            content,
+            original_filename,
+            FilePurpose::VisitAttachment,
+            None,
+            user_id,
+            &self.encryption_key,
+        )
+        .await
+        .map_err(|e| {
+            warn!("Failed to store attachment of visit {}: {}", visit_id, e);
+            AppError::Internal("Failed to store attachment".to_string())
+        })?;
+
+        let created = async {
+            let id: Uuid = sqlx::query_scalar(
+                r#"
+                INSERT INTO visit_attachments (
+                    visit_id, visit_date, patient_id, file_id, kind, caption, body_site,
+                    transcription_status, created_by
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                RETURNING id
+                "#,
+            )
+            .bind(visit_id)
+            .bind(visit.visit_date)
+            .bind(visit.patient_id)
+            .bind(file.id)
+            .bind(kind.as_str())
+            .bind(&encrypted_caption)
+            .bind(body_site)
+            .bind(transcription_status)
+            .bind(user_id)
+            .fetch_one(&mut *tx)
+            .await?;
+
+            let attachment = sqlx::query_as::<_, VisitAttachment>(&format!(
+                "{} AND a.id = $1",
+                ATTACHMENT_SELECT
+            ))
+            .bind(id)
+            .fetch_one(&mut *tx)
+            .await?;
+
+            tx.commit().await?;
+            Ok::<_, sqlx::Error>(attachment)
+        }
+        .await;
+
+        let attachment = match created {
+            Ok(attachment) => attachment,
+            Err(e) => {
+                if let Err(cleanup) = FileUploadService::hard_delete_file(&self.pool, file.id).await
+                {
+                    warn!(
+                        "Failed to remove orphaned visit attachment file {}: {}",
+                        file.id, cleanup
+                    );
+                }
+                return Err(e.into());
+            }
+        };
+
+        info!(
+            "{} attachment {} added to visit {} by {}",
+            attachment.kind, attachment.id, visit_id, user_id
+        );
+
+        Ok(VisitAttachmentResponse::new(
+            attachment,
+            caption.map(str::to_string),
+        ))
+    }
+
+    /// Attachments of a visit, oldest first
+    pub async fn list_attachments(
+        &self,
+        visit_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Vec<VisitAttachmentResponse>> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        self.find_visit(&mut tx, visit_id).await?;
+
+        let attachments = Self::visit_attachments(&mut tx, &self.encryption_key, visit_id)
+            .await
+            .map_err(|e| AppError::Internal(format!("Failed to load attachments: {}", e)))?;
+
+        tx.commit().await?;
+        Ok(attachments)
+    }
+
+    /// Attachments of every visit of a patient, by visit then upload time
+    ///
+    /// Used by the clinical summary exports.
+    pub async fn list_patient_attachments(
+        &self,
+        patient_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Vec<VisitAttachmentResponse>> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        let attachments = sqlx::query_as::<_, VisitAttachment>(&format!(
+            "{} AND a.patient_id = $1 ORDER BY a.visit_date, a.visit_id, a.created_at",
+            ATTACHMENT_SELECT
+        ))
+        .bind(patient_id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        attachments
+            .into_iter()
+            .map(|attachment| {
+                decrypt_response(&self.encryption_key, attachment)
+                    .map_err(|e| AppError::Internal(e.to_string()))
+            })
+            .collect()
+    }
+
+    /// Attachments of a visit with their captions and transcripts decrypted
+    ///
+    /// Runs on the caller's connection, so that `VisitService` can list them
+    /// with the visit under the same RLS context.
+    pub async fn visit_attachments(
+        conn: &mut PgConnection,
+        encryption_key: &EncryptionKey,
+        visit_id: Uuid,
+    ) -> anyhow::Result<Vec<VisitAttachmentResponse>> {
+        let attachments = sqlx::query_as::<_, VisitAttachment>(&format!(
+            "{} AND a.visit_id = $1 ORDER BY a.created_at",
+            ATTACHMENT_SELECT
+        ))
+        .bind(visit_id)
+        .fetch_all(conn)
+        .await?;
+
+        attachments
+            .into_iter()
+            .map(|attachment| decrypt_response(encryption_key, attachment))
+            .collect()
+    }
+
+    /// Get an attachment with its decrypted content
+    pub async fn download_attachment(
+        &self,
+        visit_id: Uuid,
+        attachment_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<(VisitAttachment, Vec<u8>)> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        let attachment = self.find(&mut tx, visit_id, attachment_id).await?;
+        tx.commit().await?;
+
+        let (_, content) = FileUploadService::get_decrypted_file_with_content(
+            &self.pool,
+            attachment.file_id,
+            &self.encryption_key,
+        )
+        .await
+        .map_err(|e| {
+            warn!("Failed to read visit attachment {}: {}", attachment_id, e);
+            AppError::Internal("Failed to read attachment".to_string())
+        })?
+        .ok_or_else(|| {
+            warn!("File of visit attachment {} is missing", attachment_id);
+            AppError::Internal("Failed to read attachment".to_string())
+        })?;
+
+        Ok((attachment, content))
+    }
+
+    /// Queue an audio memo for transcription again
+    ///
+    /// E.g. after a failure or an engine change; the previous transcript and
+    /// proposal are cleared. Allowed on signed visits too, since only the
+    /// transcription changes.
+    pub async fn requeue_transcription(
+        &self,
+        visit_id: Uuid,
+        attachment_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<VisitAttachmentResponse> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        let attachment = self.find(&mut tx, visit_id, attachment_id).await?;
+        if attachment.kind != VisitAttachmentKind::Audio.as_str() {
+            return Err(AppError::Validation(
+                "Only audio memos can be transcribed".to_string(),
+            ));
+        }
+
+        let updated = sqlx::query(
+            r#"
+            UPDATE visit_attachments
+            SET transcription_status = $2, transcript = NULL, transcript_proposal = NULL,
+                transcription_attempts = 0, transcription_error = NULL,
+                transcription_started_at = NULL, transcription_completed_at = NULL
+            WHERE id = $1 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(attachment_id)
+        .bind(TranscriptionStatus::Pending.as_str())
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        // RLS lets only doctors update attachments
+        if updated == 0 {
+            return Err(AppError::Forbidden(
+                "Only doctors can transcribe attachments again".to_string(),
+            ));
+        }
+
+        let attachment = self.find(&mut tx, visit_id, attachment_id).await?;
+        tx.commit().await?;
+
+        Ok(VisitAttachmentResponse::new(attachment, None))
+    }
+
+    /// Mark an attachment of a draft visit deleted (the encrypted file is kept)
+    pub async fn delete_attachment(
+        &self,
+        visit_id: Uuid,
+        attachment_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<()> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        self.find_draft_visit(&mut tx, visit_id).await?;
+
+        let deleted = sqlx::query(
+            r#"
+            UPDATE visit_attachments
+            SET deleted_at = NOW(), deleted_by = $3
+            WHERE id = $1 AND visit_id = $2 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(attachment_id)
+        .bind(visit_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        if deleted == 0 {
+            return Err(AppError::NotFound(format!(
+                "Attachment {} not found",
+                attachment_id
+            )));
+        }
+
+        tx.commit().await?;
+
+        info!(
+            "Attachment {} of visit {} deleted by {}",
+            attachment_id, visit_id, user_id
+        );
+
+        Ok(())
+    }
+
+    async fn find(
+        &self,
+        tx: &mut PgConnection,
+        visit_id: Uuid,
+        attachment_id: Uuid,
+    ) -> Result<VisitAttachment> {
+        sqlx::query_as::<_, VisitAttachment>(&format!(
+            "{} AND a.id = $1 AND a.visit_id = $2",
+            ATTACHMENT_SELECT
+        ))
+        .bind(attachment_id)
+        .bind(visit_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Attachment {} not found", attachment_id)))
+    }
+
+    async fn find_visit(&self, tx: &mut PgConnection, visit_id: Uuid) -> Result<AttachedVisit> {
+        let row: Option<(Uuid, NaiveDate, String)> =
+            sqlx::query_as("SELECT patient_id, visit_date, status FROM visits WHERE id = $1")
+                .bind(visit_id)
+                .fetch_optional(&mut *tx)
+                .await?;
+
+        let (patient_id, visit_date, status) =
+            row.ok_or_else(|| AppError::NotFound(format!("Visit {} not found", visit_id)))?;
+
+        Ok(AttachedVisit {
+            patient_id,
+            visit_date,
+            status,
+        })
+    }
+
+    /// The visit, which must still be a draft
+    async fn find_draft_visit(
+        &self,
+        tx: &mut PgConnection,
+        visit_id: Uuid,
+    ) -> Result<AttachedVisit> {
+        let visit = self.find_visit(tx, visit_id).await?;
+        if visit.status != "DRAFT" {
+            return Err(AppError::Conflict(format!(
+                "Visit {} is {}; attachments can only be changed on draft visits",
+                visit_id, visit.status
+            )));
+        }
+        Ok(visit)
+    }
+
+    fn encrypt(&self, value: &str) -> Result<String> {
+        self.encryption_key
+            .encrypt(value)
+            .map_err(|e| AppError::Internal(format!("Failed to encrypt caption: {}", e)))
+    }
+}
+
+/// Decrypt an attachment's caption, transcript and proposal
+fn decrypt_response(
+    encryption_key: &EncryptionKey,
+    attachment: VisitAttachment,
+) -> anyhow::Result<VisitAttachmentResponse> {
+    let id = attachment.id;
+    let caption = attachment
+        .caption
+        .as_deref()
+        .map(|caption| encryption_key.decrypt(caption))
+        .transpose()
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "Failed to decrypt caption of visit attachment {}: {}",
+                id,
+                e
+            )
+        })?;
+    let transcript = attachment
+        .transcript
+        .as_deref()
+        .map(|transcript| encryption_key.decrypt(transcript))
+        .transpose()
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "Failed to decrypt transcript of visit attachment {}: {}",
+                id,
+                e
+            )
+        })?;
+    let proposal = attachment
+        .transcript_proposal
+        .as_deref()
+        .map(|proposal| encryption_key.decrypt_json::<TranscriptProposal>(proposal))
+        .transpose()
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "Failed to decrypt transcript proposal of visit attachment {}: {}",
+                id,
+                e
+            )
+        })?;
+
+    let mut response = VisitAttachmentResponse::new(attachment, caption);
+    response.transcript = transcript;
+    response.transcript_proposal = proposal;
+    Ok(response)
+}
@@ -0,0 +1,205 @@
+/*!
+ * Visit Calculation Service
+ *
+ * Saves clinical calculator results into a visit, under RLS (doctors and
+ * nurses save, doctors delete). Results can only be added to or removed
+ * from DRAFT visits, like the rest of the visit note. The inputs and the
+ * result are encrypted.
+ */
+
+use crate::{
+    db::rls::begin_with_rls,
+    models::{VisitCalculation, VisitCalculationResponse},
+    services::clinical_calculators::CalculatorInput,
+    utils::{encryption::EncryptionKey, AppError, Result},
+};
+use chrono::NaiveDate;
+use sqlx::{PgConnection, PgPool};
+use tracing::info;
+use uuid::Uuid;
+
+const CALCULATION_COLUMNS: &str = r#"
+    id, visit_id, visit_date, patient_id, calculator, inputs, result, created_by, created_at
+"#;
+
+/// Visit calculation service
+pub struct VisitCalculationService {
+    pool: PgPool,
+    encryption_key: EncryptionKey,
+}
+
+impl VisitCalculationService {
+    /// Create a new visit calculation service
+    pub fn new(pool: PgPool, encryption_key: EncryptionKey) -> Self {
+        Self {
+            pool,
+            encryption_key,
+        }
+    }
+
+    /// Calculations saved into a visit, in the order they were made
+    pub async fn list_calculations(
+        &self,
+        visit_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Vec<VisitCalculationResponse>> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        self.find_visit(&mut tx, visit_id).await?;
+
+        let calculations = sqlx::query_as::<_, VisitCalculation>(&format!(
+            r#"
+            SELECT {}
+            FROM visit_calculations
+            WHERE visit_id = $1 AND deleted_at IS NULL
+            ORDER BY created_at
+            "#,
+            CALCULATION_COLUMNS
+        ))
+        .bind(visit_id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        calculations
+            .iter()
+            .map(|c| self.decrypt_calculation(c))
+            .collect()
+    }
+
+    /// Compute a calculator and save the result into a draft visit
+    pub async fn save_calculation(
+        &self,
+        visit_id: Uuid,
+        input: &CalculatorInput,
+        user_id: Uuid,
+    ) -> Result<VisitCalculationResponse> {
+        let result = input.calculate()?;
+
+        let inputs_json = serde_json::to_value(input)
+            .map(|mut value| value["inputs"].take())
+            .map_err(|e| AppError::Internal(format!("Failed to serialize inputs: {}", e)))?;
+        let inputs = self.encrypt(&inputs_json.to_string())?;
+        let result_json = serde_json::to_string(&result)
+            .map_err(|e| AppError::Internal(format!("Failed to serialize result: {}", e)))?;
+        let encrypted_result = self.encrypt(&result_json)?;
+
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        let (patient_id, visit_date) = self.find_draft_visit(&mut tx, visit_id).await?;
+
+        let calculation = sqlx::query_as::<_, VisitCalculation>(&format!(
+            r#"
+            INSERT INTO visit_calculations (
+                visit_id, visit_date, patient_id, calculator, inputs, result, created_by
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING {}
+            "#,
+            CALCULATION_COLUMNS
+        ))
+        .bind(visit_id)
+        .bind(visit_date)
+        .bind(patient_id)
+        .bind(input.calculator().as_str())
+        .bind(&inputs)
+        .bind(&encrypted_result)
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        info!(
+            "{} calculation {} saved into visit {} by {}",
+            calculation.calculator, calculation.id, visit_id, user_id
+        );
+
+        self.decrypt_calculation(&calculation)
+    }
+
+    /// Delete a calculation from a draft visit
+    ///
+    /// The row is marked deleted.
+    pub async fn delete_calculation(
+        &self,
+        visit_id: Uuid,
+        calculation_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<()> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        self.find_draft_visit(&mut tx, visit_id).await?;
+
+        let result = sqlx::query(
+            r#"
+            UPDATE visit_calculations
+            SET deleted_at = NOW(), deleted_by = $3
+            WHERE id = $1 AND visit_id = $2 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(calculation_id)
+        .bind(visit_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound(format!(
+                "Calculation {} not found",
+                calculation_id
+            )));
+        }
+
+        tx.commit().await?;
+
+        info!(
+            "Calculation {} deleted from visit {} by {}",
+            calculation_id, visit_id, user_id
+        );
+
+        Ok(())
+    }
+
+    /// Patient, date and status of a visit
+    async fn find_visit(
+        &self,
+        tx: &mut PgConnection,
+        visit_id: Uuid,
+    ) -> Result<(Uuid, NaiveDate, String)> {
+        sqlx::query_as("SELECT patient_id, visit_date, status FROM visits WHERE id = $1")
+            .bind(visit_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Visit {} not found", visit_id)))
+    }
+
+    /// Patient and date of a visit that can still be edited
+    async fn find_draft_visit(
+        &self,
+        tx: &mut PgConnection,
+        visit_id: Uuid,
+    ) -> Result<(Uuid, NaiveDate)> {
+        let (patient_id, visit_date, status) = self.find_visit(tx, visit_id).await?;
+        if status != "DRAFT" {
+            return Err(AppError::Conflict(format!(
+                "Visit {} is {} and can no longer be changed",
+                visit_id, status
+            )));
+        }
+        Ok((patient_id, visit_date))
+    }
+
+    fn decrypt_calculation(
+        &self,
+        calculation: &VisitCalculation,
+    ) -> Result<VisitCalculationResponse> {
+        calculation
+            .decrypt(&self.encryption_key)
+            .map_err(|e| AppError::Internal(format!("Failed to decrypt calculation: {}", e)))
+    }
+
+    fn encrypt(&self, value: &str) -> Result<String> {
+        self.encryption_key
+            .encrypt(value)
+            .map_err(|e| AppError::Internal(format!("Failed to encrypt calculation: {}", e)))
+    }
+}
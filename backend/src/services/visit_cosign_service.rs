@@ -0,0 +1,353 @@
+/*!
+ * Visit Co-Signature Service
+ *
+ * Co-signature requirements and the co-sign queue. A user with a
+ * requirement (a trainee doctor, or a nurse documenting visits) signs into
+ * PENDING_COSIGN; the supervisor named here countersigns or returns the
+ * visit through `VisitService`. Requirements hold no patient data; the
+ * queue is read under RLS, so a doctor only sees the visits waiting for
+ * them.
+ */
+
+use crate::{
+    db::rls::begin_with_rls,
+    models::{
+        CosignQueueItem, CosignQueueRow, CosignRequirement, SetCosignRequirementRequest, UserRole,
+    },
+    utils::{encryption::EncryptionKey, AppError, Result},
+};
+use chrono::Utc;
+use sqlx::{PgConnection, PgPool};
+use tracing::info;
+use uuid::Uuid;
+
+const REQUIREMENT_COLUMNS: &str = r#"
+    r.user_id, u.first_name || ' ' || u.last_name AS user_name, u.role AS user_role,
+    r.supervisor_id, s.first_name || ' ' || s.last_name AS supervisor_name,
+    r.created_by, r.created_at, r.updated_at
+"#;
+
+/// Role and state of a user named in a requirement
+struct CosignParty {
+    role: UserRole,
+    is_active: bool,
+    supervised: bool,
+}
+
+/// Visit co-signature service
+pub struct VisitCosignService {
+    pool: PgPool,
+    encryption_key: EncryptionKey,
+}
+
+impl VisitCosignService {
+    /// Create a new visit co-signature service
+    pub fn new(pool: PgPool, encryption_key: EncryptionKey) -> Self {
+        Self {
+            pool,
+            encryption_key,
+        }
+    }
+
+    /// All co-signature requirements, by user name
+    pub async fn list_requirements(&self) -> Result<Vec<CosignRequirement>> {
+        let requirements = sqlx::query_as::<_, CosignRequirement>(&format!(
+            r#"
+            SELECT {}
+            FROM visit_cosign_requirements r
+            JOIN users u ON u.id = r.user_id
+            JOIN users s ON s.id = r.supervisor_id
+            ORDER BY u.last_name, u.first_name
+            "#,
+            REQUIREMENT_COLUMNS
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(requirements)
+    }
+
+    /// Require co-signature of a user's visits by a supervisor, replacing
+    /// any previous supervisor
+    ///
+    /// Visits already waiting stay with the supervisor they were sent to.
+    pub async fn set_requirement(
+        &self,
+        user_id: Uuid,
+        request: &SetCosignRequirementRequest,
+        set_by: Uuid,
+    ) -> Result<CosignRequirement> {
+        let mut tx = self.pool.begin().await?;
+
+        let user = self
+            .find_party(&mut tx, user_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("User {} not found", user_id)))?;
+        let supervisor = self
+            .find_party(&mut tx, request.supervisor_id)
+            .await?
+            .ok_or_else(|| {
+                AppError::Validation(format!("Supervisor {} not found", request.supervisor_id))
+            })?;
+        check_requirement(user_id, &user, request.supervisor_id, &supervisor)?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO visit_cosign_requirements (user_id, supervisor_id, created_by)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (user_id) DO UPDATE SET supervisor_id = EXCLUDED.supervisor_id
+            "#,
+        )
+        .bind(user_id)
+        .bind(request.supervisor_id)
+        .bind(set_by)
+        .execute(&mut *tx)
+        .await?;
+
+        let requirement = sqlx::query_as::<_, CosignRequirement>(&format!(
+            r#"
+            SELECT {}
+            FROM visit_cosign_requirements r
+            JOIN users u ON u.id = r.user_id
+            JOIN users s ON s.id = r.supervisor_id
+            WHERE r.user_id = $1
+            "#,
+            REQUIREMENT_COLUMNS
+        ))
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        info!(
+            "Visits of user {} now require co-signature by {} (set by {})",
+            user_id, request.supervisor_id, set_by
+        );
+
+        Ok(requirement)
+    }
+
+    /// Remove a user's co-signature requirement
+    ///
+    /// Visits already waiting still need their supervisor's co-signature.
+    pub async fn remove_requirement(&self, user_id: Uuid) -> Result<()> {
+        let result = sqlx::query("DELETE FROM visit_cosign_requirements WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound(format!(
+                "User {} has no co-signature requirement",
+                user_id
+            )));
+        }
+
+        info!("Co-signature requirement removed for user {}", user_id);
+        Ok(())
+    }
+
+    /// Visits awaiting co-signature, oldest first
+    ///
+    /// `supervisor_id` narrows the queue to one supervisor; RLS already
+    /// limits doctors to the visits sent to them.
+    pub async fn queue(
+        &self,
+        supervisor_id: Option<Uuid>,
+        user_id: Uuid,
+    ) -> Result<Vec<CosignQueueItem>> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        let rows = sqlx::query_as::<_, CosignQueueRow>(
+            r#"
+            SELECT
+                v.id AS visit_id, v.visit_date, v.visit_type, v.patient_id,
+                p.first_name AS patient_first_name, p.last_name AS patient_last_name,
+                v.provider_id, v.signed_by,
+                su.first_name || ' ' || su.last_name AS signed_by_name,
+                v.signed_at, v.cosign_supervisor_id,
+                sv.first_name || ' ' || sv.last_name AS supervisor_name
+            FROM visits v
+            LEFT JOIN patients p ON p.id = v.patient_id
+            LEFT JOIN users su ON su.id = v.signed_by
+            LEFT JOIN users sv ON sv.id = v.cosign_supervisor_id
+            WHERE v.status = 'PENDING_COSIGN'
+              AND ($1::UUID IS NULL OR v.cosign_supervisor_id = $1)
+            ORDER BY v.signed_at
+            "#,
+        )
+        .bind(supervisor_id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        let now = Utc::now();
+        rows.into_iter()
+            .map(|row| -> Result<CosignQueueItem> {
+                let patient_name = match (&row.patient_first_name, &row.patient_last_name) {
+                    (Some(first), Some(last)) => {
+                        Some(format!("{} {}", self.decrypt(first)?, self.decrypt(last)?))
+                    }
+                    _ => None,
+                };
+
+                Ok(CosignQueueItem {
+                    visit_id: row.visit_id,
+                    visit_date: row.visit_date,
+                    visit_type: row.visit_type,
+                    patient_id: row.patient_id,
+                    patient_name,
+                    provider_id: row.provider_id,
+                    signed_by: row.signed_by,
+                    signed_by_name: row.signed_by_name,
+                    signed_at: row.signed_at,
+                    cosign_supervisor_id: row.cosign_supervisor_id,
+                    supervisor_name: row.supervisor_name,
+                    waiting_days: (now - row.signed_at).num_days().max(0),
+                })
+            })
+            .collect()
+    }
+
+    async fn find_party(
+        &self,
+        tx: &mut PgConnection,
+        user_id: Uuid,
+    ) -> Result<Option<CosignParty>> {
+        let row: Option<(UserRole, bool, bool)> = sqlx::query_as(
+            r#"
+            SELECT u.role, u.is_active,
+                   EXISTS (SELECT 1 FROM visit_cosign_requirements r WHERE r.user_id = u.id)
+            FROM users u
+            WHERE u.id = $1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        Ok(row.map(|(role, is_active, supervised)| CosignParty {
+            role,
+            is_active,
+            supervised,
+        }))
+    }
+
+    fn decrypt(&self, value: &str) -> Result<String> {
+        self.encryption_key
+            .decrypt(value)
+            .map_err(|e| AppError::Internal(format!("Failed to decrypt patient name: {}", e)))
+    }
+}
+
+/// Who may be supervised, and by whom
+///
+/// Doctors and nurses can be supervised; the supervisor is an active doctor
+/// (or admin) who is not under supervision themselves.
+fn check_requirement(
+    user_id: Uuid,
+    user: &CosignParty,
+    supervisor_id: Uuid,
+    supervisor: &CosignParty,
+) -> Result<()> {
+    if user_id == supervisor_id {
+        return Err(AppError::Validation(
+            "A user cannot supervise themselves".to_string(),
+        ));
+    }
+    if !matches!(user.role, UserRole::Doctor | UserRole::Nurse) {
+        return Err(AppError::Validation(
+            "Only doctors and nurses can require co-signature".to_string(),
+        ));
+    }
+    if !matches!(supervisor.role, UserRole::Doctor | UserRole::Admin) {
+        return Err(AppError::Validation(
+            "The supervisor must be a doctor".to_string(),
+        ));
+    }
+    if !supervisor.is_active {
+        return Err(AppError::Validation(
+            "The supervisor's account is not active".to_string(),
+        ));
+    }
+    if supervisor.supervised {
+        return Err(AppError::Validation(
+            "The supervisor requires co-signature themselves".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn party(role: UserRole) -> CosignParty {
+        CosignParty {
+            role,
+            is_active: true,
+            supervised: false,
+        }
+    }
+
+    #[test]
+    fn test_check_requirement() {
+        let (trainee, supervisor) = (Uuid::new_v4(), Uuid::new_v4());
+
+        assert!(check_requirement(
+            trainee,
+            &party(UserRole::Doctor),
+            supervisor,
+            &party(UserRole::Doctor)
+        )
+        .is_ok());
+        assert!(check_requirement(
+            trainee,
+            &party(UserRole::Nurse),
+            supervisor,
+            &party(UserRole::Admin)
+        )
+        .is_ok());
+
+        // Self-supervision, receptionists and nurse supervisors are rejected
+        assert!(check_requirement(
+            trainee,
+            &party(UserRole::Doctor),
+            trainee,
+            &party(UserRole::Doctor)
+        )
+        .is_err());
+        assert!(check_requirement(
+            trainee,
+            &party(UserRole::Receptionist),
+            supervisor,
+            &party(UserRole::Doctor)
+        )
+        .is_err());
+        assert!(check_requirement(
+            trainee,
+            &party(UserRole::Doctor),
+            supervisor,
+            &party(UserRole::Nurse)
+        )
+        .is_err());
+
+        // Inactive or supervised supervisors are rejected
+        let inactive = CosignParty {
+            is_active: false,
+            ..party(UserRole::Doctor)
+        };
+        assert!(
+            check_requirement(trainee, &party(UserRole::Doctor), supervisor, &inactive).is_err()
+        );
+        let supervised = CosignParty {
+            supervised: true,
+            ..party(UserRole::Doctor)
+        };
+        assert!(
+            check_requirement(trainee, &party(UserRole::Doctor), supervisor, &supervised).is_err()
+        );
+    }
+}
@@ -0,0 +1,409 @@
+/*!
+ * Visit Note Search Service
+ *
+ * Full-text search over the encrypted narrative fields of visits. When a
+ * visit is saved, its notes are split into normalized words and each word
+ * is replaced by a keyed hash (`EncryptionKey::blind_index`); the hashes,
+ * with the position and a weight per field, form the `search_vector` of
+ * the visit in `visit_search_index`. A query is hashed the same way, so
+ * PostgreSQL ranks matches without ever seeing a word of the notes. The
+ * matching visits are then decrypted to highlight the words found.
+ *
+ * The hashes only hide the words, not how often the same word appears.
+ * Visits saved before the index existed (or whose indexing failed) are
+ * picked up by `spawn_visit_search_indexer`.
+ */
+
+use crate::db::rls::{
+    apply_rls_context, begin_with_rls, include_trashed_records, SYSTEM_ROLE, SYSTEM_USER_ID,
+};
+use crate::models::{
+    Visit, VisitSearchHighlight, VisitSearchHit, VisitSearchQuery, VisitSearchResponse,
+};
+use crate::utils::{encryption::EncryptionKey, text_search, AppError, Result};
+use sqlx::{FromRow, PgConnection, PgPool};
+use std::collections::{BTreeMap, HashMap};
+use tokio::time::{sleep, Duration};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// Pause between runs of the indexing job
+const POLL_INTERVAL_SECS: u64 = 300;
+
+/// Visits indexed per run
+const BATCH_SIZE: i64 = 200;
+
+/// Hex characters of the blind index kept as lexeme (64 bits)
+const LEXEME_HEX_CHARS: usize = 16;
+
+/// Highest word position a tsvector stores
+const MAX_POSITION: usize = 16_383;
+
+/// Positions a tsvector stores per lexeme
+const MAX_POSITIONS_PER_LEXEME: usize = 256;
+
+/// Most words in a search query
+const MAX_QUERY_TERMS: usize = 10;
+
+/// Visit found by a search, with its rank
+#[derive(Debug, FromRow)]
+struct RankedVisit {
+    #[sqlx(flatten)]
+    visit: Visit,
+    rank: f32,
+}
+
+/// Visit note search service
+pub struct VisitSearchService {
+    pool: PgPool,
+    encryption_key: EncryptionKey,
+}
+
+impl VisitSearchService {
+    /// Create a new visit note search service
+    pub fn new(pool: PgPool, encryption_key: EncryptionKey) -> Self {
+        Self {
+            pool,
+            encryption_key,
+        }
+    }
+
+    /// Visits whose notes contain every word of the query, best match first
+    ///
+    /// Only visits the user may read are found (RLS on `visits`), and none
+    /// in the recycle bin.
+    pub async fn search(
+        &self,
+        query: &VisitSearchQuery,
+        user_id: Uuid,
+    ) -> Result<VisitSearchResponse> {
+        let terms = text_search::search_terms(&query.q);
+        if terms.is_empty() {
+            return Err(AppError::Validation(format!(
+                "Search needs at least one word of {} or more characters",
+                text_search::MIN_TERM_CHARS
+            )));
+        }
+        if terms.len() > MAX_QUERY_TERMS {
+            return Err(AppError::Validation(format!(
+                "Search is limited to {} words",
+                MAX_QUERY_TERMS
+            )));
+        }
+        let tsquery = search_query(&self.encryption_key, &terms);
+        let limit = query.limit();
+        let offset = query.offset();
+
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        let total: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*)
+            FROM visit_search_index i
+            JOIN visits v ON v.id = i.visit_id AND v.visit_date = i.visit_date
+            WHERE i.search_vector @@ $1::tsquery
+              AND ($2::UUID IS NULL OR v.patient_id = $2)
+            "#,
+        )
+        .bind(&tsquery)
+        .bind(query.patient_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let rows = sqlx::query_as::<_, RankedVisit>(
+            r#"
+            SELECT v.*, ts_rank(i.search_vector, $1::tsquery) AS rank
+            FROM visit_search_index i
+            JOIN visits v ON v.id = i.visit_id AND v.visit_date = i.visit_date
+            WHERE i.search_vector @@ $1::tsquery
+              AND ($2::UUID IS NULL OR v.patient_id = $2)
+            ORDER BY rank DESC, v.visit_date DESC, v.id
+            LIMIT $3 OFFSET $4
+            "#,
+        )
+        .bind(&tsquery)
+        .bind(query.patient_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let patient_ids: Vec<Uuid> = rows.iter().map(|r| r.visit.patient_id).collect();
+        let patients: Vec<(Uuid, String, String)> =
+            sqlx::query_as("SELECT id, first_name, last_name FROM patients WHERE id = ANY($1)")
+                .bind(&patient_ids)
+                .fetch_all(&mut *tx)
+                .await?;
+
+        let provider_ids: Vec<Uuid> = rows.iter().map(|r| r.visit.provider_id).collect();
+        let providers: HashMap<Uuid, String> = sqlx::query_as::<_, (Uuid, String)>(
+            "SELECT id, first_name || ' ' || last_name FROM users WHERE id = ANY($1)",
+        )
+        .bind(&provider_ids)
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .collect();
+
+        tx.commit().await?;
+
+        let mut patient_names = HashMap::new();
+        for (id, first_name, last_name) in patients {
+            patient_names.insert(
+                id,
+                format!(
+                    "{} {}",
+                    self.decrypt(&first_name)?,
+                    self.decrypt(&last_name)?
+                ),
+            );
+        }
+
+        let mut results = Vec::with_capacity(rows.len());
+        for RankedVisit { visit, rank } in rows {
+            let mut highlights = Vec::new();
+            for (field, _, encrypted) in narrative(&visit) {
+                let Some(encrypted) = encrypted else { continue };
+                if let Some(snippet) = text_search::highlight(&self.decrypt(encrypted)?, &terms) {
+                    highlights.push(VisitSearchHighlight {
+                        field: field.to_string(),
+                        snippet,
+                    });
+                }
+            }
+
+            results.push(VisitSearchHit {
+                visit_id: visit.id,
+                patient_id: visit.patient_id,
+                patient_name: patient_names.get(&visit.patient_id).cloned(),
+                provider_id: visit.provider_id,
+                provider_name: providers.get(&visit.provider_id).cloned(),
+                visit_date: visit.visit_date,
+                visit_type: visit.visit_type,
+                status: visit.status,
+                rank,
+                highlights,
+            });
+        }
+
+        Ok(VisitSearchResponse {
+            results,
+            total,
+            limit,
+            offset,
+        })
+    }
+
+    /// Index a batch of visits that have no index entry yet, returning how
+    /// many were indexed
+    ///
+    /// A visit whose notes cannot be decrypted is indexed without words, so
+    /// it is not retried on every run.
+    pub async fn index_missing(&self) -> Result<usize> {
+        let mut tx = self.pool.begin().await?;
+        apply_rls_context(&mut tx, SYSTEM_USER_ID, SYSTEM_ROLE).await?;
+        include_trashed_records(&mut tx).await?;
+
+        let visits = sqlx::query_as::<_, Visit>(
+            r#"
+            SELECT v.* FROM visits v
+            WHERE NOT EXISTS (SELECT 1 FROM visit_search_index i WHERE i.visit_id = v.id)
+            ORDER BY v.created_at
+            LIMIT $1
+            "#,
+        )
+        .bind(BATCH_SIZE)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        for visit in &visits {
+            let vector = search_vector(&self.encryption_key, visit).unwrap_or_else(|e| {
+                warn!("Visit {} indexed without its notes: {}", visit.id, e);
+                String::new()
+            });
+            upsert_entry(&mut tx, visit, &vector).await?;
+        }
+
+        tx.commit().await?;
+        Ok(visits.len())
+    }
+
+    fn decrypt(&self, value: &str) -> Result<String> {
+        self.encryption_key.decrypt(value).map_err(|e| {
+            AppError::Internal(format!("Failed to decrypt visit search result: {}", e))
+        })
+    }
+}
+
+/// Index the notes of a visit, replacing its previous entry
+///
+/// Called in the transaction that saves the visit, so the index never lags
+/// behind the notes.
+pub async fn index_visit(
+    conn: &mut PgConnection,
+    encryption_key: &EncryptionKey,
+    visit: &Visit,
+) -> Result<()> {
+    let vector = search_vector(encryption_key, visit)?;
+    upsert_entry(conn, visit, &vector).await
+}
+
+async fn upsert_entry(conn: &mut PgConnection, visit: &Visit, vector: &str) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO visit_search_index (visit_id, visit_date, search_vector)
+        VALUES ($1, $2, $3::tsvector)
+        ON CONFLICT (visit_id) DO UPDATE
+        SET visit_date = EXCLUDED.visit_date,
+            search_vector = EXCLUDED.search_vector,
+            indexed_at = NOW()
+        "#,
+    )
+    .bind(visit.id)
+    .bind(visit.visit_date)
+    .bind(vector)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Narrative fields of a visit (still encrypted) in indexing order, with
+/// their rank weight
+fn narrative(visit: &Visit) -> [(&'static str, char, Option<&str>); 9] {
+    [
+        ("chief_complaint", 'A', visit.chief_complaint.as_deref()),
+        ("assessment", 'A', visit.assessment.as_deref()),
+        (
+            "history_present_illness",
+            'B',
+            visit.history_present_illness.as_deref(),
+        ),
+        ("subjective", 'B', visit.subjective.as_deref()),
+        ("objective", 'B', visit.objective.as_deref()),
+        ("physical_exam", 'B', visit.physical_exam.as_deref()),
+        ("plan", 'C', visit.plan.as_deref()),
+        ("clinical_notes", 'C', visit.clinical_notes.as_deref()),
+        ("follow_up_notes", 'C', visit.follow_up_notes.as_deref()),
+    ]
+}
+
+/// tsvector (in its text form) of the decrypted notes of a visit
+fn search_vector(encryption_key: &EncryptionKey, visit: &Visit) -> Result<String> {
+    let mut texts = Vec::new();
+    for (_, weight, encrypted) in narrative(visit) {
+        if let Some(encrypted) = encrypted {
+            let text = encryption_key
+                .decrypt(encrypted)
+                .map_err(|e| AppError::Internal(format!("Failed to decrypt visit notes: {}", e)))?;
+            texts.push((weight, text));
+        }
+    }
+    Ok(build_search_vector(encryption_key, &texts))
+}
+
+/// tsvector of weighted texts, words replaced by their hashed lexeme
+fn build_search_vector(encryption_key: &EncryptionKey, texts: &[(char, String)]) -> String {
+    let mut lexemes: HashMap<String, String> = HashMap::new();
+    let mut positions: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut position = 0;
+
+    for (weight, text) in texts {
+        for (_, word) in text_search::words(text) {
+            position = (position + 1).min(MAX_POSITION);
+            let hashed = lexemes
+                .entry(word)
+                .or_insert_with_key(|word| lexeme(encryption_key, word));
+            let entry = positions.entry(hashed.clone()).or_default();
+            if entry.len() < MAX_POSITIONS_PER_LEXEME {
+                entry.push(format!("{}{}", position, weight));
+            }
+        }
+    }
+
+    positions
+        .iter()
+        .map(|(lexeme, positions)| format!("'{}':{}", lexeme, positions.join(",")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// tsquery (in its text form) matching every term
+fn search_query(encryption_key: &EncryptionKey, terms: &[String]) -> String {
+    terms
+        .iter()
+        .map(|term| format!("'{}'", lexeme(encryption_key, term)))
+        .collect::<Vec<_>>()
+        .join(" & ")
+}
+
+/// Hashed stand-in for a normalized word of the notes
+fn lexeme(encryption_key: &EncryptionKey, word: &str) -> String {
+    let mut index = encryption_key.field_blind_index("visit-notes", word);
+    index.truncate(LEXEME_HEX_CHARS);
+    index
+}
+
+/// Spawn the background job indexing visits that have no index entry
+pub fn spawn_visit_search_indexer(pool: PgPool, encryption_key: EncryptionKey) {
+    let service = VisitSearchService::new(pool, encryption_key);
+
+    tokio::spawn(async move {
+        loop {
+            match service.index_missing().await {
+                Ok(indexed) if indexed as i64 == BATCH_SIZE => continue,
+                Ok(0) => {}
+                Ok(indexed) => info!("Indexed the notes of {} visits for search", indexed),
+                Err(e) => error!("Visit search indexing failed: {}", e),
+            }
+            sleep(Duration::from_secs(POLL_INTERVAL_SECS)).await;
+        }
+    });
+
+    info!("Visit search indexer spawned as background task");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+
+    fn test_key() -> EncryptionKey {
+        std::env::set_var("ENCRYPTION_KEY", BASE64.encode([0u8; 32]));
+        EncryptionKey::from_env().unwrap()
+    }
+
+    #[test]
+    fn test_build_search_vector() {
+        let key = test_key();
+        let vector = build_search_vector(
+            &key,
+            &[
+                ('A', "Carpal tunnel".to_string()),
+                ('C', "EMG for the carpal tunnel".to_string()),
+            ],
+        );
+
+        let carpal = lexeme(&key, "carpal");
+        assert_eq!(carpal.len(), LEXEME_HEX_CHARS);
+        assert!(vector.contains(&format!("'{}':1A,6C", carpal)));
+        assert!(vector.contains(&format!("'{}':2A,7C", lexeme(&key, "tunnel"))));
+        assert!(!vector.to_lowercase().contains("carpal"));
+        assert_eq!(vector.matches(':').count(), 5);
+
+        assert_eq!(build_search_vector(&key, &[]), "");
+    }
+
+    #[test]
+    fn test_search_query() {
+        let key = test_key();
+        let terms = text_search::search_terms("Carpal TUNNEL");
+        assert_eq!(
+            search_query(&key, &terms),
+            format!(
+                "'{}' & '{}'",
+                lexeme(&key, "carpal"),
+                lexeme(&key, "tunnel")
+            )
+        );
+    }
+}
@@ -9,15 +9,18 @@
  * - Audit logging
  */
 
+use crate::db::rls::{begin_with_rls, target_trash_record};
 use crate::models::{
     AuditAction, AuditLog, CreateAuditLog, CreateVisitRequest, EntityType, RequestContext,
     UpdateVisitRequest, Visit, VisitResponse, VisitStatus, VisitType,
 };
+use crate::services::{visit_search_service::index_visit, PatientAllergyService, VisitAttachmentService};
+use crate::utils::concurrency::{is_stale, version_conflict};
 use crate::utils::encryption::EncryptionKey;
 use anyhow::{Context, Result};
 use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgPool};
 use uuid::Uuid;
 use validator::Validate;
 
@@ -54,6 +57,7 @@ impl Default for VisitSearchFilter {
 pub struct VisitStatistics {
     pub total_visits: i64,
     pub drafts: i64,
+    pub pending_cosign: i64,
     pub signed: i64,
     pub locked: i64,
     pub by_type: Vec<VisitTypeCount>,
@@ -91,53 +95,23 @@ impl VisitService {
         }
     }
 
-    /// Helper to set RLS context within a transaction
-    async fn set_rls_context(
-        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
-        user_id: Uuid,
-    ) -> Result<()> {
-        // Query the user's role from the database
-        let role: String = sqlx::query_scalar(
-            "SELECT role::TEXT FROM users WHERE id = $1"
-        )
-        .bind(user_id)
-        .fetch_one(&mut **tx)
-        .await
-        .context("Failed to fetch user role for RLS context")?;
-
-        // Set RLS context variables using set_config() for parameterized queries
-        sqlx::query("SELECT set_config('app.current_user_id', $1, true)")
-            .bind(user_id.to_string())
-            .execute(&mut **tx)
-            .await
-            .context("Failed to set RLS user context")?;
-
-        sqlx::query("SELECT set_config('app.current_user_role', $1, true)")
-            .bind(&role)
-            .execute(&mut **tx)
-            .await
-            .context("Failed to set RLS role context")?;
-
-        Ok(())
-    }
-
     /// Helper to decrypt a visit and enrich it with patient/provider names
     async fn decrypt_with_names(&self, visit: &Visit) -> Result<VisitResponse> {
         self.decrypt_with_names_using_pool(visit, &self.pool).await
     }
 
     /// Helper to decrypt a visit within a transaction context (for RLS)
-    async fn decrypt_with_names_in_tx<'a>(
+    async fn decrypt_with_names_in_tx(
         &self,
         visit: &Visit,
-        tx: &mut sqlx::Transaction<'a, sqlx::Postgres>,
+        tx: &mut PgConnection,
     ) -> Result<VisitResponse> {
         // Fetch patient name (encrypted in patients table, need to decrypt)
         let patient_names_encrypted: Option<(String, String)> = sqlx::query_as(
             "SELECT first_name, last_name FROM patients WHERE id = $1"
         )
         .bind(visit.patient_id)
-        .fetch_optional(&mut **tx)
+        .fetch_optional(&mut *tx)
         .await
         .context("Failed to fetch patient name")?;
 
@@ -158,7 +132,7 @@ impl VisitService {
             "SELECT first_name, last_name FROM users WHERE id = $1"
         )
         .bind(visit.provider_id)
-        .fetch_optional(&mut **tx)
+        .fetch_optional(&mut *tx)
         .await
         .context("Failed to fetch provider name")?;
 
@@ -168,7 +142,7 @@ impl VisitService {
                 "SELECT first_name, last_name FROM users WHERE id = $1"
             )
             .bind(signed_by_id)
-            .fetch_optional(&mut **tx)
+            .fetch_optional(&mut *tx)
             .await
             .context("Failed to fetch signed_by name")?;
             names.map(|(f, l)| format!("{} {}", f, l))
@@ -263,10 +237,9 @@ impl VisitService {
             .context("Invalid provider_id format")?;
 
         // Start transaction for RLS context and insert
-        let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-
-        // Set RLS context
-        Self::set_rls_context(&mut tx, created_by_id).await?;
+        let mut tx = begin_with_rls(&self.pool, created_by_id)
+            .await
+            .context("Failed to begin transaction")?;
 
         // Encrypt vitals if present
         let encrypted_vitals = if let Some(mut vitals) = data.vitals {
@@ -388,6 +361,11 @@ impl VisitService {
         .await
         .context("Failed to create visit")?;
 
+        // Index the notes for search
+        index_visit(&mut tx, &self.encryption_key, &visit)
+            .await
+            .context("Failed to index visit notes")?;
+
         // Audit log
         let _ = AuditLog::create(
             &self.pool,
@@ -419,10 +397,9 @@ impl VisitService {
         request_ctx: Option<&RequestContext>,
     ) -> Result<Option<VisitResponse>> {
         // Start transaction for RLS context
-        let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-
-        // Set RLS context
-        Self::set_rls_context(&mut tx, user_id).await?;
+        let mut tx = begin_with_rls(&self.pool, user_id)
+            .await
+            .context("Failed to begin transaction")?;
 
         let visit = sqlx::query_as::<_, Visit>(
             r#"
@@ -455,7 +432,27 @@ impl VisitService {
                 .await;
 
                 // Decrypt within transaction to maintain RLS context for patient name lookup
-                Some(self.decrypt_with_names_in_tx(v, &mut tx).await?)
+                let mut response = self.decrypt_with_names_in_tx(v, &mut tx).await?;
+
+                // Allergies are shown with the visit (empty for roles that cannot read them)
+                response.patient_allergies = Some(
+                    PatientAllergyService::active_allergies(
+                        &mut tx,
+                        &self.encryption_key,
+                        v.patient_id,
+                    )
+                    .await
+                    .context("Failed to fetch patient allergies")?,
+                );
+
+                // Attachments are listed with the visit (empty for roles that cannot read them)
+                response.attachments = Some(
+                    VisitAttachmentService::visit_attachments(&mut tx, &self.encryption_key, v.id)
+                        .await
+                        .context("Failed to fetch visit attachments")?,
+                );
+
+                Some(response)
             }
             None => None,
         };
@@ -479,14 +476,15 @@ impl VisitService {
             .context("Invalid visit update data")?;
 
         // Start transaction for RLS context and update
-        let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-
-        // Set RLS context
-        Self::set_rls_context(&mut tx, updated_by_id).await?;
+        let mut tx = begin_with_rls(&self.pool, updated_by_id)
+            .await
+            .context("Failed to begin transaction")?;
 
-        // Check if visit exists and is editable
+        // Check if visit exists and is editable; the row is held until commit
+        // so a concurrent edit cannot slip between the version check and the
+        // update
         let existing = sqlx::query_as::<_, Visit>(
-            "SELECT * FROM visits WHERE id = $1"
+            "SELECT * FROM visits WHERE id = $1 FOR UPDATE"
         )
         .bind(id)
         .fetch_optional(&mut *tx)
@@ -494,6 +492,11 @@ impl VisitService {
         .context("Failed to fetch visit")?
         .ok_or_else(|| anyhow::anyhow!("Visit not found"))?;
 
+        if is_stale(data.expected_version, existing.updated_at) {
+            let current = self.decrypt_with_names_in_tx(&existing, &mut tx).await?;
+            return Err(version_conflict(&current).into());
+        }
+
         if !existing.can_edit() {
             anyhow::bail!(
                 "Cannot edit visit with status {:?}. Only DRAFT visits can be edited.",
@@ -620,6 +623,10 @@ impl VisitService {
         .await
         .context("Failed to update visit")?;
 
+        index_visit(&mut tx, &self.encryption_key, &visit)
+            .await
+            .context("Failed to index visit notes")?;
+
         // Audit log
         let _ = AuditLog::create(
             &self.pool,
@@ -643,6 +650,9 @@ impl VisitService {
     }
 
     /// Delete visit (only DRAFT visits can be deleted)
+    ///
+    /// The visit goes to the recycle bin, where it can be restored until the
+    /// RECYCLE_BIN retention rule purges it.
     pub async fn delete_visit(
         &self,
         id: Uuid,
@@ -650,15 +660,20 @@ impl VisitService {
         request_ctx: Option<&RequestContext>,
     ) -> Result<()> {
         // Start transaction for RLS context and delete
-        let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-
-        // Set RLS context
-        Self::set_rls_context(&mut tx, user_id).await?;
+        let mut tx = begin_with_rls(&self.pool, user_id)
+            .await
+            .context("Failed to begin transaction")?;
+        target_trash_record(&mut tx, id).await?;
 
         let result = sqlx::query(
-            "DELETE FROM visits WHERE id = $1 AND status = 'DRAFT'"
+            r#"
+            UPDATE visits
+            SET deleted_at = NOW(), deleted_by = $2
+            WHERE id = $1 AND status = 'DRAFT' AND deleted_at IS NULL
+            "#,
         )
         .bind(id)
+        .bind(user_id)
         .execute(&mut *tx)
         .await
         .context("Failed to delete visit")?;
@@ -696,10 +711,9 @@ impl VisitService {
         user_id: Uuid,
     ) -> Result<Vec<VisitResponse>> {
         // Start transaction for RLS context
-        let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-
-        // Set RLS context
-        Self::set_rls_context(&mut tx, user_id).await?;
+        let mut tx = begin_with_rls(&self.pool, user_id)
+            .await
+            .context("Failed to begin transaction")?;
 
         let limit = filter.limit.unwrap_or(20).min(100);
         let offset = filter.offset.unwrap_or(0);
@@ -758,10 +772,9 @@ impl VisitService {
         user_id: Uuid,
     ) -> Result<i64> {
         // Start transaction for RLS context
-        let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-
-        // Set RLS context
-        Self::set_rls_context(&mut tx, user_id).await?;
+        let mut tx = begin_with_rls(&self.pool, user_id)
+            .await
+            .context("Failed to begin transaction")?;
 
         // Build a simple count query with RLS - the RLS policies will filter automatically
         let count: (i64,) = if let Some(patient_id) = filter.patient_id {
@@ -802,10 +815,9 @@ impl VisitService {
         user_id: Uuid,
     ) -> Result<serde_json::Value> {
         // Start transaction for RLS context
-        let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-
-        // Set RLS context
-        Self::set_rls_context(&mut tx, user_id).await?;
+        let mut tx = begin_with_rls(&self.pool, user_id)
+            .await
+            .context("Failed to begin transaction")?;
 
         // Count total visits
         let total: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM visits")
@@ -852,10 +864,9 @@ impl VisitService {
         offset: Option<i64>,
     ) -> Result<Vec<VisitResponse>> {
         // Start transaction for RLS context
-        let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-
-        // Set RLS context
-        Self::set_rls_context(&mut tx, user_id).await?;
+        let mut tx = begin_with_rls(&self.pool, user_id)
+            .await
+            .context("Failed to begin transaction")?;
 
         let limit = limit.unwrap_or(50).min(100);
         let offset = offset.unwrap_or(0);
@@ -888,6 +899,9 @@ impl VisitService {
     }
 
     /// Sign a visit (DRAFT → SIGNED)
+    ///
+    /// If the signer has a co-signature requirement the visit goes to
+    /// PENDING_COSIGN instead, waiting for their supervisor.
     pub async fn sign_visit(
         &self,
         id: Uuid,
@@ -895,10 +909,9 @@ impl VisitService {
         request_ctx: Option<&RequestContext>,
     ) -> Result<VisitResponse> {
         // Start transaction for RLS context and sign operation
-        let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-
-        // Set RLS context
-        Self::set_rls_context(&mut tx, signed_by).await?;
+        let mut tx = begin_with_rls(&self.pool, signed_by)
+            .await
+            .context("Failed to begin transaction")?;
 
         // Fetch the visit
         let visit = sqlx::query_as::<_, Visit>(
@@ -910,8 +923,20 @@ impl VisitService {
         .context("Failed to fetch visit")?
         .ok_or_else(|| anyhow::anyhow!("Visit not found"))?;
 
-        // Defense-in-depth: verify the signing user owns this visit
-        if visit.provider_id != signed_by {
+        // A signer under supervision sends the visit to their supervisor
+        let cosign_supervisor_id: Option<Uuid> = sqlx::query_scalar(
+            "SELECT supervisor_id FROM visit_cosign_requirements WHERE user_id = $1"
+        )
+        .bind(signed_by)
+        .fetch_optional(&mut *tx)
+        .await
+        .context("Failed to fetch co-signature requirement")?;
+
+        // Defense-in-depth: verify the signing user owns this visit (or, under
+        // supervision, documented it)
+        let documented_by_signer =
+            cosign_supervisor_id.is_some() && visit.created_by == Some(signed_by);
+        if visit.provider_id != signed_by && !documented_by_signer {
             anyhow::bail!("Cannot sign another provider's visit");
         }
 
@@ -926,15 +951,23 @@ impl VisitService {
         // Generate signature hash from SOAP notes
         let signature_hash = self.generate_signature_hash(&visit)?;
 
-        // Update visit to SIGNED status
+        let new_status = if cosign_supervisor_id.is_some() {
+            VisitStatus::PendingCosign
+        } else {
+            VisitStatus::Signed
+        };
+
+        // Update visit to SIGNED (or PENDING_COSIGN) status
         // The database trigger will auto-set signed_at, signed_by, and signature_hash
         let signed_visit = sqlx::query_as::<_, Visit>(
             r#"
             UPDATE visits SET
-                status = 'SIGNED',
+                status = $4,
                 signed_by = $2,
                 signed_at = NOW(),
                 signature_hash = $3,
+                cosign_supervisor_id = $5,
+                cosign_return_note = NULL,
                 updated_by = $2,
                 updated_at = NOW()
             WHERE id = $1
@@ -944,6 +977,8 @@ impl VisitService {
         .bind(id)
         .bind(signed_by)
         .bind(signature_hash)
+        .bind(new_status)
+        .bind(cosign_supervisor_id)
         .fetch_one(&mut *tx)
         .await
         .context("Failed to sign visit")?;
@@ -961,7 +996,12 @@ impl VisitService {
                 entity_id: Some(id.to_string()),
                 changes: Some(serde_json::json!({
                     "action": "signed",
-                    "status_change": "DRAFT -> SIGNED"
+                    "status_change": if cosign_supervisor_id.is_some() {
+                        "DRAFT -> PENDING_COSIGN"
+                    } else {
+                        "DRAFT -> SIGNED"
+                    },
+                    "cosign_supervisor_id": cosign_supervisor_id,
                 })),
                 ip_address: request_ctx.and_then(|c| c.ip_address.clone()),
                 user_agent: request_ctx.and_then(|c| c.user_agent.clone()),
@@ -981,10 +1021,9 @@ impl VisitService {
         request_ctx: Option<&RequestContext>,
     ) -> Result<VisitResponse> {
         // Start transaction for RLS context and lock operation
-        let mut tx = self.pool.begin().await.context("Failed to begin transaction")?;
-
-        // Set RLS context
-        Self::set_rls_context(&mut tx, locked_by).await?;
+        let mut tx = begin_with_rls(&self.pool, locked_by)
+            .await
+            .context("Failed to begin transaction")?;
 
         // Fetch the visit
         let visit = sqlx::query_as::<_, Visit>(
@@ -1052,6 +1091,168 @@ impl VisitService {
         self.decrypt_with_names(&locked_visit).await
     }
 
+    /// Countersign a visit awaiting co-signature (PENDING_COSIGN → SIGNED)
+    ///
+    /// Only the supervisor the visit was submitted to may countersign, or an
+    /// admin standing in for them; nobody countersigns their own signature.
+    pub async fn cosign_visit(
+        &self,
+        id: Uuid,
+        cosigned_by: Uuid,
+        is_admin: bool,
+        request_ctx: Option<&RequestContext>,
+    ) -> Result<VisitResponse> {
+        let mut tx = begin_with_rls(&self.pool, cosigned_by)
+            .await
+            .context("Failed to begin transaction")?;
+
+        let visit = self.fetch_pending_cosign(&mut tx, id, cosigned_by, is_admin).await?;
+
+        let cosigned_visit = sqlx::query_as::<_, Visit>(
+            r#"
+            UPDATE visits SET
+                status = 'SIGNED',
+                cosigned_by = $2,
+                cosigned_at = NOW(),
+                updated_by = $2,
+                updated_at = NOW()
+            WHERE id = $1 AND status = 'PENDING_COSIGN'
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(cosigned_by)
+        .fetch_one(&mut *tx)
+        .await
+        .context("Failed to co-sign visit")?;
+
+        tx.commit().await.context("Failed to commit transaction")?;
+
+        let _ = AuditLog::create(
+            &self.pool,
+            CreateAuditLog {
+                user_id: Some(cosigned_by),
+                action: AuditAction::Update,
+                entity_type: EntityType::Visit,
+                entity_id: Some(id.to_string()),
+                changes: Some(serde_json::json!({
+                    "action": "cosigned",
+                    "status_change": "PENDING_COSIGN -> SIGNED",
+                    "signed_by": visit.signed_by,
+                    "cosign_supervisor_id": visit.cosign_supervisor_id,
+                })),
+                ip_address: request_ctx.and_then(|c| c.ip_address.clone()),
+                user_agent: request_ctx.and_then(|c| c.user_agent.clone()),
+                request_id: request_ctx.map(|c| c.request_id),
+            },
+        )
+        .await;
+
+        self.decrypt_with_names(&cosigned_visit).await
+    }
+
+    /// Return a visit awaiting co-signature to its author (PENDING_COSIGN → DRAFT)
+    ///
+    /// The first signature is cleared; the note explains what to change and
+    /// is kept until the visit is signed again.
+    pub async fn return_visit_for_revision(
+        &self,
+        id: Uuid,
+        returned_by: Uuid,
+        is_admin: bool,
+        note: &str,
+        request_ctx: Option<&RequestContext>,
+    ) -> Result<VisitResponse> {
+        let encrypted_note = self
+            .encryption_key
+            .encrypt(note)
+            .context("Failed to encrypt return note")?;
+
+        let mut tx = begin_with_rls(&self.pool, returned_by)
+            .await
+            .context("Failed to begin transaction")?;
+
+        let visit = self.fetch_pending_cosign(&mut tx, id, returned_by, is_admin).await?;
+
+        let returned_visit = sqlx::query_as::<_, Visit>(
+            r#"
+            UPDATE visits SET
+                status = 'DRAFT',
+                signed_by = NULL,
+                signed_at = NULL,
+                signature_hash = NULL,
+                cosign_return_note = $3,
+                updated_by = $2,
+                updated_at = NOW()
+            WHERE id = $1 AND status = 'PENDING_COSIGN'
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(returned_by)
+        .bind(&encrypted_note)
+        .fetch_one(&mut *tx)
+        .await
+        .context("Failed to return visit")?;
+
+        tx.commit().await.context("Failed to commit transaction")?;
+
+        // The note may contain clinical details; it is not audited
+        let _ = AuditLog::create(
+            &self.pool,
+            CreateAuditLog {
+                user_id: Some(returned_by),
+                action: AuditAction::Update,
+                entity_type: EntityType::Visit,
+                entity_id: Some(id.to_string()),
+                changes: Some(serde_json::json!({
+                    "action": "returned_for_revision",
+                    "status_change": "PENDING_COSIGN -> DRAFT",
+                    "signed_by": visit.signed_by,
+                })),
+                ip_address: request_ctx.and_then(|c| c.ip_address.clone()),
+                user_agent: request_ctx.and_then(|c| c.user_agent.clone()),
+                request_id: request_ctx.map(|c| c.request_id),
+            },
+        )
+        .await;
+
+        self.decrypt_with_names(&returned_visit).await
+    }
+
+    /// Fetch a visit the user may countersign or return
+    async fn fetch_pending_cosign(
+        &self,
+        tx: &mut PgConnection,
+        id: Uuid,
+        user_id: Uuid,
+        is_admin: bool,
+    ) -> Result<Visit> {
+        let visit = sqlx::query_as::<_, Visit>("SELECT * FROM visits WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await
+            .context("Failed to fetch visit")?
+            .ok_or_else(|| anyhow::anyhow!("Visit not found"))?;
+
+        if visit.status != VisitStatus::PendingCosign {
+            anyhow::bail!(
+                "Cannot co-sign visit with status {:?}. Only visits awaiting co-signature can be countersigned or returned.",
+                visit.status
+            );
+        }
+
+        if visit.signed_by == Some(user_id) {
+            anyhow::bail!("Cannot co-sign a visit you signed yourself");
+        }
+
+        if !is_admin && visit.cosign_supervisor_id != Some(user_id) {
+            anyhow::bail!("Cannot co-sign visit: it awaits another supervisor");
+        }
+
+        Ok(visit)
+    }
+
     /// Generate signature hash from encrypted SOAP notes
     /// Uses SHA-256 hash of concatenated SOAP notes (encrypted content)
     fn generate_signature_hash(&self, visit: &Visit) -> Result<String> {
@@ -1092,6 +1293,7 @@ impl VisitService {
 
         let mut total = 0i64;
         let mut drafts = 0i64;
+        let mut pending_cosign = 0i64;
         let mut signed = 0i64;
         let mut locked = 0i64;
 
@@ -1099,6 +1301,7 @@ impl VisitService {
             total += count;
             match status.as_str() {
                 "DRAFT" => drafts = count,
+                "PENDING_COSIGN" => pending_cosign = count,
                 "SIGNED" => signed = count,
                 "LOCKED" => locked = count,
                 _ => {}
@@ -1132,6 +1335,7 @@ impl VisitService {
         Ok(VisitStatistics {
             total_visits: total,
             drafts,
+            pending_cosign,
             signed,
             locked,
             by_type,
@@ -1322,6 +1526,14 @@ impl VisitService {
         .await
         .context("Failed to restore visit")?;
 
+        let mut tx = begin_with_rls(&self.pool, restored_by)
+            .await
+            .context("Failed to begin transaction")?;
+        index_visit(&mut tx, &self.encryption_key, &restored)
+            .await
+            .context("Failed to index visit notes")?;
+        tx.commit().await.context("Failed to commit transaction")?;
+
         // Decrypt and convert to response with names
         self.decrypt_with_names(&restored).await
     }
@@ -1421,6 +1633,7 @@ mod tests {
         let stats = VisitStatistics {
             total_visits: 100,
             drafts: 30,
+            pending_cosign: 0,
             signed: 50,
             locked: 20,
             by_type: vec![],
@@ -1439,6 +1652,7 @@ mod tests {
         let stats = VisitStatistics {
             total_visits: 50,
             drafts: 10,
+            pending_cosign: 0,
             signed: 25,
             locked: 15,
             by_type: vec![
@@ -1580,6 +1794,7 @@ mod tests {
         let stats = VisitStatistics {
             total_visits: 0,
             drafts: 0,
+            pending_cosign: 0,
             signed: 0,
             locked: 0,
             by_type: vec![],
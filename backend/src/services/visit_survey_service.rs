@@ -0,0 +1,92 @@
+/*!
+ * Visit Survey Service
+ *
+ * Records answers to post-visit surveys. Surveys are created and emailed
+ * by the notification scheduler; an answer comes from the public survey
+ * link and is written as the patient (the portal role with
+ * app.portal_patient_id set), which may answer its own surveys once and
+ * change nothing else.
+ */
+
+use crate::{
+    db::rls::begin_portal_transaction,
+    models::{VisitSurveyAnswerResponse, VisitSurveyClaims},
+    utils::{encryption::EncryptionKey, AppError, Result},
+};
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+/// Visit survey service
+pub struct VisitSurveyService {
+    pool: PgPool,
+    encryption_key: EncryptionKey,
+}
+
+impl VisitSurveyService {
+    pub fn new(pool: PgPool, encryption_key: EncryptionKey) -> Self {
+        Self {
+            pool,
+            encryption_key,
+        }
+    }
+
+    /// Record the answer to the survey named by `claims`
+    ///
+    /// The comment is encrypted; a blank one is not stored. Answers 404 for
+    /// an unknown survey and 409 if it was already answered.
+    pub async fn record_answer(
+        &self,
+        claims: &VisitSurveyClaims,
+        score: i16,
+        comment: Option<&str>,
+    ) -> Result<VisitSurveyAnswerResponse> {
+        let comment = comment
+            .map(str::trim)
+            .filter(|c| !c.is_empty())
+            .map(|c| {
+                self.encryption_key
+                    .encrypt(c)
+                    .map_err(|e| AppError::Internal(format!("Failed to encrypt comment: {}", e)))
+            })
+            .transpose()?;
+
+        let mut tx = begin_portal_transaction(&self.pool, claims.patient_id).await?;
+
+        let responded_at: Option<DateTime<Utc>> = sqlx::query_scalar(
+            "SELECT responded_at FROM visit_surveys WHERE id = $1 AND patient_id = $2 FOR UPDATE",
+        )
+        .bind(claims.survey_id)
+        .bind(claims.patient_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound("This link is invalid or has expired".to_string()))?;
+
+        if responded_at.is_some() {
+            return Err(AppError::Conflict(
+                "This survey has already been answered".to_string(),
+            ));
+        }
+
+        let responded_at: DateTime<Utc> = sqlx::query_scalar(
+            r#"
+            UPDATE visit_surveys
+            SET score = $2, comment = $3, responded_at = NOW()
+            WHERE id = $1
+            RETURNING responded_at
+            "#,
+        )
+        .bind(claims.survey_id)
+        .bind(score)
+        .bind(&comment)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(VisitSurveyAnswerResponse {
+            score,
+            responded_at,
+            message: "Thank you for your feedback.".to_string(),
+        })
+    }
+}
@@ -0,0 +1,301 @@
+/*!
+ * Visit Transcription Service
+ *
+ * Background job transcribing the audio memos of visits with the configured
+ * `TranscriptionEngine`. The transcript is stored encrypted and split into
+ * a proposal for the visit's subjective and objective fields: the user
+ * reviews it and applies it through the normal visit update, so nothing is
+ * ever written into the visit note by the job.
+ *
+ * Memos are claimed with `FOR UPDATE SKIP LOCKED`, so several instances can
+ * run the job. One left PROCESSING by a crashed instance is claimed again
+ * after `STALE_AFTER_MINUTES`. Failures are retried on later runs up to
+ * `MAX_ATTEMPTS`; formats the engine cannot read are SKIPPED.
+ */
+
+use crate::db::rls::{apply_rls_context, SYSTEM_ROLE, SYSTEM_USER_ID};
+use crate::models::{TranscriptProposal, TranscriptionStatus};
+use crate::services::{transcription::TranscriptionEngine, FileUploadService};
+use crate::utils::encryption::EncryptionKey;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use sqlx::{PgPool, Postgres, Transaction};
+use std::sync::Arc;
+use tokio::time::{sleep, Duration};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// Pause between runs
+const POLL_INTERVAL_SECS: u64 = 30;
+
+/// Memos processed per run (transcription is slow)
+const BATCH_SIZE: i64 = 3;
+
+/// Attempts before a memo is marked FAILED
+const MAX_ATTEMPTS: i32 = 3;
+
+/// PROCESSING memos older than this are claimed again
+const STALE_AFTER_MINUTES: i32 = 60;
+
+/// Spoken cue that the dictation moves on to the examination findings
+static OBJECTIVE_CUE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)\b(?:esame obiettivo|obiettivamente|all'esame|on examination|objective)\b|\be\.o\.",
+    )
+    .unwrap()
+});
+
+/// Split a dictated transcript into the visit's subjective and objective
+/// fields
+///
+/// Whatever is dictated before the first examination cue ("esame
+/// obiettivo", "obiettivamente", "all'esame", "E.O.") is history; the rest
+/// is the examination. Without a cue the whole transcript is proposed as
+/// subjective.
+pub fn propose_sections(transcript: &str) -> TranscriptProposal {
+    let non_empty = |text: &str| Some(text.trim().to_string()).filter(|t| !t.is_empty());
+
+    match OBJECTIVE_CUE.find(transcript) {
+        Some(cue) => TranscriptProposal {
+            subjective: non_empty(&transcript[..cue.start()]),
+            objective: non_empty(&transcript[cue.start()..]),
+        },
+        None => TranscriptProposal {
+            subjective: non_empty(transcript),
+            objective: None,
+        },
+    }
+}
+
+/// Audio memo claimed for transcription
+#[derive(sqlx::FromRow)]
+struct TranscriptionJob {
+    id: Uuid,
+    file_id: Uuid,
+    transcription_attempts: i32,
+}
+
+/// Visit audio memo transcription job
+pub struct VisitTranscriptionService {
+    pool: PgPool,
+    encryption_key: EncryptionKey,
+    engine: Arc<dyn TranscriptionEngine>,
+}
+
+impl VisitTranscriptionService {
+    /// Create a new visit transcription service
+    pub fn new(
+        pool: PgPool,
+        encryption_key: EncryptionKey,
+        engine: Arc<dyn TranscriptionEngine>,
+    ) -> Self {
+        Self {
+            pool,
+            encryption_key,
+            engine,
+        }
+    }
+
+    /// Transcribe a batch of queued audio memos
+    ///
+    /// Returns how many memos were claimed.
+    pub async fn run_batch(&self) -> anyhow::Result<usize> {
+        let mut tx = self.begin_system_tx().await?;
+        let jobs = sqlx::query_as::<_, TranscriptionJob>(
+            r#"
+            UPDATE visit_attachments
+            SET transcription_status = 'PROCESSING', transcription_started_at = NOW(),
+                transcription_attempts = transcription_attempts + 1
+            WHERE id IN (
+                SELECT id FROM visit_attachments
+                WHERE deleted_at IS NULL
+                  AND kind = 'AUDIO'
+                  AND (transcription_status = 'PENDING'
+                       OR (transcription_status = 'PROCESSING'
+                           AND transcription_started_at < NOW() - make_interval(mins => $2)))
+                ORDER BY created_at
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, file_id, transcription_attempts
+            "#,
+        )
+        .bind(BATCH_SIZE)
+        .bind(STALE_AFTER_MINUTES)
+        .fetch_all(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        for job in &jobs {
+            if let Err(e) = self.process(job).await {
+                let status = if job.transcription_attempts >= MAX_ATTEMPTS {
+                    TranscriptionStatus::Failed
+                } else {
+                    TranscriptionStatus::Pending
+                };
+                warn!(
+                    "Transcription of visit attachment {} failed (attempt {}): {:#}",
+                    job.id, job.transcription_attempts, e
+                );
+                let message = format!("{:#}", e);
+                if let Err(e) = self
+                    .finish(job.id, status, None, None, Some(&message))
+                    .await
+                {
+                    error!(
+                        "Failed to record transcription failure of visit attachment {}: {}",
+                        job.id, e
+                    );
+                }
+            }
+        }
+
+        Ok(jobs.len())
+    }
+
+    async fn process(&self, job: &TranscriptionJob) -> anyhow::Result<()> {
+        let (file, content) = FileUploadService::get_decrypted_file_with_content(
+            &self.pool,
+            job.file_id,
+            &self.encryption_key,
+        )
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))?
+        .ok_or_else(|| anyhow::anyhow!("File {} is missing", job.file_id))?;
+
+        if !self.engine.supports(&file.mime_type) {
+            return self
+                .finish(job.id, TranscriptionStatus::Skipped, None, None, None)
+                .await;
+        }
+
+        let transcript = self.engine.transcribe(&content, &file.mime_type).await?;
+        let transcript = transcript.trim();
+        let proposal = propose_sections(transcript);
+
+        let encrypted_transcript = self.encryption_key.encrypt(transcript)?;
+        let encrypted_proposal = self.encryption_key.encrypt_json(&proposal)?;
+
+        self.finish(
+            job.id,
+            TranscriptionStatus::Completed,
+            Some(&encrypted_transcript),
+            Some(&encrypted_proposal),
+            None,
+        )
+        .await?;
+
+        info!(
+            "Transcription of visit attachment {} completed with {} ({} characters)",
+            job.id,
+            self.engine.name(),
+            transcript.len()
+        );
+        Ok(())
+    }
+
+    /// Record the outcome, unless the memo was re-queued meanwhile
+    async fn finish(
+        &self,
+        attachment_id: Uuid,
+        status: TranscriptionStatus,
+        encrypted_transcript: Option<&str>,
+        encrypted_proposal: Option<&str>,
+        error_message: Option<&str>,
+    ) -> anyhow::Result<()> {
+        let mut tx = self.begin_system_tx().await?;
+        sqlx::query(
+            r#"
+            UPDATE visit_attachments
+            SET transcription_status = $2, transcript = $3, transcript_proposal = $4,
+                transcription_error = $5,
+                transcription_completed_at = CASE WHEN $2 = 'PENDING' THEN NULL ELSE NOW() END
+            WHERE id = $1 AND transcription_status = 'PROCESSING'
+            "#,
+        )
+        .bind(attachment_id)
+        .bind(status.as_str())
+        .bind(encrypted_transcript)
+        .bind(encrypted_proposal)
+        .bind(error_message)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn begin_system_tx(&self) -> Result<Transaction<'static, Postgres>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        apply_rls_context(&mut tx, SYSTEM_USER_ID, SYSTEM_ROLE).await?;
+        Ok(tx)
+    }
+}
+
+/// Spawn the visit transcription job as a background task
+///
+/// Runs every `POLL_INTERVAL_SECS`, and straight again while there is a
+/// backlog.
+pub fn spawn_visit_transcription_job(
+    pool: PgPool,
+    encryption_key: EncryptionKey,
+    engine: Arc<dyn TranscriptionEngine>,
+) {
+    let engine_name = engine.name();
+    let service = VisitTranscriptionService::new(pool, encryption_key, engine);
+
+    tokio::spawn(async move {
+        loop {
+            match service.run_batch().await {
+                Ok(claimed) if claimed as i64 == BATCH_SIZE => continue,
+                Ok(_) => {}
+                Err(e) => error!("Visit transcription job failed: {}", e),
+            }
+            sleep(Duration::from_secs(POLL_INTERVAL_SECS)).await;
+        }
+    });
+
+    info!(
+        "Visit transcription job spawned as background task ({})",
+        engine_name
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_propose_sections_splits_at_examination_cue() {
+        let proposal = propose_sections(
+            "Paziente riferisce cefalea da tre giorni, non febbre. \
+             Esame obiettivo: PA 130/80, collo libero.",
+        );
+        assert_eq!(
+            proposal.subjective.as_deref(),
+            Some("Paziente riferisce cefalea da tre giorni, non febbre.")
+        );
+        assert_eq!(
+            proposal.objective.as_deref(),
+            Some("Esame obiettivo: PA 130/80, collo libero.")
+        );
+
+        let proposal = propose_sections("E.O. murmure vescicolare conservato.");
+        assert_eq!(proposal.subjective, None);
+        assert_eq!(
+            proposal.objective.as_deref(),
+            Some("E.O. murmure vescicolare conservato.")
+        );
+    }
+
+    #[test]
+    fn test_propose_sections_without_cue() {
+        let proposal = propose_sections("  Controllo dopo terapia, sta meglio.\n");
+        assert_eq!(
+            proposal.subjective.as_deref(),
+            Some("Controllo dopo terapia, sta meglio.")
+        );
+        assert_eq!(proposal.objective, None);
+
+        assert_eq!(propose_sections("   "), TranscriptProposal::default());
+    }
+}
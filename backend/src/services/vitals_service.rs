@@ -0,0 +1,166 @@
+/*!
+ * Vitals Service
+ *
+ * Vital signs trends across a patient's visits, under RLS. Vitals are stored
+ * encrypted on each visit; this service decrypts them in date order and adds
+ * growth percentiles for visits before the patient turned 20.
+ */
+
+use crate::{
+    db::rls::begin_with_rls,
+    models::{
+        patient::Gender, visit::VitalSigns, vitals_trend::age_in_months, VitalsTrendPoint,
+        VitalsTrendQuery, VitalsTrendResponse,
+    },
+    utils::{encryption::EncryptionKey, growth_charts::is_pediatric, AppError, Result},
+};
+use chrono::{NaiveDate, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Maximum visits in a trend
+const TREND_LIMIT: i64 = 1000;
+
+/// Vitals service
+pub struct VitalsService {
+    pool: PgPool,
+    encryption_key: EncryptionKey,
+}
+
+impl VitalsService {
+    /// Create a new vitals service
+    pub fn new(pool: PgPool, encryption_key: EncryptionKey) -> Self {
+        Self {
+            pool,
+            encryption_key,
+        }
+    }
+
+    /// Vital signs of every visit of the patient that recorded them, oldest
+    /// first
+    pub async fn get_trends(
+        &self,
+        patient_id: Uuid,
+        query: &VitalsTrendQuery,
+        user_id: Uuid,
+    ) -> Result<VitalsTrendResponse> {
+        if let (Some(from), Some(to)) = (query.date_from, query.date_to) {
+            if from > to {
+                return Err(AppError::Validation(
+                    "date_from must not be after date_to".to_string(),
+                ));
+            }
+        }
+
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        let (date_of_birth, gender): (String, Gender) =
+            sqlx::query_as("SELECT date_of_birth, gender FROM patients WHERE id = $1")
+                .bind(patient_id)
+                .fetch_optional(&mut *tx)
+                .await?
+                .ok_or_else(|| AppError::NotFound(format!("Patient {} not found", patient_id)))?;
+
+        let visits: Vec<(Uuid, NaiveDate, String)> = sqlx::query_as(
+            r#"
+            SELECT id, visit_date, vitals
+            FROM visits
+            WHERE patient_id = $1
+              AND vitals IS NOT NULL
+              AND ($2::DATE IS NULL OR visit_date >= $2)
+              AND ($3::DATE IS NULL OR visit_date <= $3)
+            ORDER BY visit_date, visit_time
+            LIMIT $4
+            "#,
+        )
+        .bind(patient_id)
+        .bind(query.date_from)
+        .bind(query.date_to)
+        .bind(TREND_LIMIT)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        // Anonymized patients keep no usable date of birth
+        let date_of_birth = self
+            .encryption_key
+            .decrypt(&date_of_birth)
+            .ok()
+            .and_then(|dob| NaiveDate::parse_from_str(&dob, "%Y-%m-%d").ok());
+
+        let mut points = Vec::with_capacity(visits.len());
+        for (visit_id, visit_date, encrypted) in visits {
+            let vitals = self.decrypt_vitals(&encrypted)?;
+            points.push(VitalsTrendPoint::new(
+                visit_id,
+                visit_date,
+                vitals,
+                date_of_birth,
+                &gender,
+            ));
+        }
+
+        let today = Utc::now().date_naive();
+        Ok(VitalsTrendResponse {
+            patient_id,
+            pediatric: date_of_birth
+                .map(|dob| is_pediatric(age_in_months(dob, today)))
+                .unwrap_or(false),
+            points,
+        })
+    }
+
+    /// Vital signs of the patient's most recent visit that recorded them
+    pub async fn get_latest(
+        &self,
+        patient_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Option<VitalsTrendPoint>> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        let latest: Option<(Uuid, NaiveDate, String, String, Gender)> = sqlx::query_as(
+            r#"
+            SELECT v.id, v.visit_date, v.vitals, p.date_of_birth, p.gender
+            FROM visits v
+            JOIN patients p ON p.id = v.patient_id
+            WHERE v.patient_id = $1 AND v.vitals IS NOT NULL
+            ORDER BY v.visit_date DESC, v.visit_time DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(patient_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        let Some((visit_id, visit_date, encrypted, date_of_birth, gender)) = latest else {
+            return Ok(None);
+        };
+
+        let date_of_birth = self
+            .encryption_key
+            .decrypt(&date_of_birth)
+            .ok()
+            .and_then(|dob| NaiveDate::parse_from_str(&dob, "%Y-%m-%d").ok());
+        let vitals = self.decrypt_vitals(&encrypted)?;
+
+        Ok(Some(VitalsTrendPoint::new(
+            visit_id,
+            visit_date,
+            vitals,
+            date_of_birth,
+            &gender,
+        )))
+    }
+
+    fn decrypt_vitals(&self, encrypted: &str) -> Result<VitalSigns> {
+        let json = self
+            .encryption_key
+            .decrypt(encrypted)
+            .map_err(|e| AppError::Internal(format!("Failed to decrypt vitals: {}", e)))?;
+        serde_json::from_str(&json)
+            .map_err(|e| AppError::Internal(format!("Failed to parse vitals: {}", e)))
+    }
+}
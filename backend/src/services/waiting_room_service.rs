@@ -0,0 +1,61 @@
+/*!
+ * Waiting Room Service
+ *
+ * The queue of patients who have arrived (status ARRIVED) and wait to be
+ * seen. Staff see the appointments the appointment policies let them see.
+ */
+
+use crate::{
+    db::rls::begin_with_rls,
+    models::{WaitingRoomQuery, WaitingRoomQueue, WaitingRoomRow},
+    utils::Result,
+};
+use chrono::{DateTime, Utc};
+use chrono_tz::Europe::Rome;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Waiting room service
+pub struct WaitingRoomService {
+    pool: PgPool,
+}
+
+impl WaitingRoomService {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Waiting room queue of a day (today by default), Europe/Rome
+    pub async fn queue(
+        &self,
+        query: &WaitingRoomQuery,
+        user_id: Uuid,
+        now: DateTime<Utc>,
+    ) -> Result<WaitingRoomQueue> {
+        let date = query
+            .date
+            .unwrap_or_else(|| now.with_timezone(&Rome).date_naive());
+
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        let rows = sqlx::query_as::<_, WaitingRoomRow>(
+            r#"
+            SELECT a.id AS appointment_id, a.patient_id, p.medical_record_number,
+                   a.provider_id, a.type, a.scheduled_start, a.checked_in_at
+            FROM appointments a
+            JOIN patients p ON p.id = a.patient_id
+            WHERE a.status = 'ARRIVED'
+              AND a.checked_in_at IS NOT NULL
+              AND (a.scheduled_start AT TIME ZONE 'Europe/Rome')::DATE = $1
+              AND ($2::UUID IS NULL OR a.provider_id = $2)
+            ORDER BY a.scheduled_start, a.checked_in_at
+            "#,
+        )
+        .bind(date)
+        .bind(query.provider_id)
+        .fetch_all(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        Ok(WaitingRoomQueue::from_rows(date, rows, now))
+    }
+}
@@ -0,0 +1,886 @@
+/*!
+ * Waitlist Service
+ *
+ * Manages the waitlist and offers freed slots to it. Cancelling a future
+ * appointment while patients wait opens a waitlist slot (a trigger on
+ * `appointments`). A background job then, every minute:
+ * - Expires waitlist entries past their last day and closes slots that
+ *   start too soon to be offered or were taken by another booking.
+ * - Expires offers not answered in time, and supersedes the pending offers
+ *   of closed slots.
+ * - Offers each open slot without a pending offer to the next matching
+ *   patients, in the order they joined the list, by email with signed
+ *   accept and decline links.
+ *
+ * The links answer through `respond`. Accepting locks the slot, so the
+ * first patient to accept books the appointment and the others find the
+ * slot filled. The job and the links act as the system user, since the
+ * patient is not signed in; staff act with their own RLS context.
+ */
+
+use crate::{
+    db::rls::{apply_rls_context, begin_with_rls, SYSTEM_ROLE, SYSTEM_USER_ID},
+    models::{
+        waitlist::WAITLIST_OFFER_CUTOFF_MINUTES, AppointmentType, CreateAppointmentRequest,
+        CreateWaitlistEntryRequest, WaitlistEntry, WaitlistEntryStatus, WaitlistOffer,
+        WaitlistOfferAction, WaitlistOfferClaims, WaitlistOfferOutcome, WaitlistOfferStatus,
+        WaitlistQuery, WaitlistSettings, MAX_WAITLIST_OFFERS_PER_ROUND,
+        WAITLIST_OFFERS_PER_ROUND_SETTING_KEY, WAITLIST_OFFER_EXPIRY_SETTING_KEY,
+    },
+    services::{
+        AppointmentService, BrandingService, EmailService, NotificationService, SettingsService,
+    },
+    utils::{AppError, EncryptionKey, Result},
+};
+use chrono::{DateTime, Utc};
+use chrono_tz::Europe::Rome;
+use sqlx::{Acquire, FromRow, PgConnection, PgPool};
+use std::sync::Arc;
+use tokio::time::{sleep, Duration as TokioDuration};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// Seconds between runs of the waitlist job
+const CHECK_INTERVAL_SECS: u64 = 60;
+
+/// Open slots offered per run
+const SLOT_BATCH_SIZE: i64 = 20;
+
+/// Entries listed at most
+const MAX_ENTRIES_LISTED: i64 = 500;
+
+const ENTRY_COLUMNS: &str = r#"
+    w.id, w.patient_id, p.medical_record_number, w.provider_id, w.appointment_type,
+    w.duration_minutes, w.earliest_date, w.latest_date, w.status, w.booked_appointment_id,
+    w.closed_at, w.created_by, w.created_at, w.updated_at,
+    (SELECT COUNT(*) FROM waitlist_offers o WHERE o.waitlist_entry_id = w.id) AS offers_sent,
+    (SELECT MAX(o.expires_at) FROM waitlist_offers o
+     WHERE o.waitlist_entry_id = w.id AND o.status = 'PENDING') AS pending_offer_expires_at
+"#;
+
+/// Open slot awaiting offers
+#[derive(FromRow)]
+struct OpenSlot {
+    id: Uuid,
+    provider_id: Uuid,
+    appointment_type: AppointmentType,
+    slot_start: DateTime<Utc>,
+    slot_end: DateTime<Utc>,
+    /// Patient of the cancelled appointment, who is not offered it back
+    cancelled_patient_id: Uuid,
+}
+
+/// Waitlisted patient matching a slot (names and email encrypted)
+#[derive(FromRow)]
+struct Candidate {
+    entry_id: Uuid,
+    patient_id: Uuid,
+    first_name: String,
+    last_name: String,
+    email: Option<String>,
+    email_address_override: Option<String>,
+}
+
+/// Offer being answered, with its slot and entry
+#[derive(FromRow)]
+struct OfferForUpdate {
+    id: Uuid,
+    slot_id: Uuid,
+    waitlist_entry_id: Uuid,
+    status: WaitlistOfferStatus,
+    appointment_id: Option<Uuid>,
+    provider_id: Uuid,
+    slot_type: AppointmentType,
+    slot_start: DateTime<Utc>,
+    slot_end: DateTime<Utc>,
+    slot_status: String,
+    requested_type: Option<AppointmentType>,
+    duration_minutes: i32,
+    entry_status: WaitlistEntryStatus,
+    entry_created_by: Uuid,
+}
+
+/// Spawn the background job offering freed slots to the waitlist
+pub fn spawn_waitlist_job(
+    pool: PgPool,
+    email_service: EmailService,
+    encryption_key: EncryptionKey,
+    settings_service: Arc<SettingsService>,
+) {
+    let notification_service = NotificationService::new(pool.clone(), email_service);
+    let service = WaitlistService::new(pool, encryption_key);
+
+    tokio::spawn(async move {
+        loop {
+            let settings = WaitlistService::settings(&settings_service).await;
+            match service.run(&notification_service, &settings).await {
+                Ok(0) => {}
+                Ok(count) => info!("Sent {} waitlist offers", count),
+                Err(e) => error!("Waitlist job failed: {}", e),
+            }
+
+            sleep(TokioDuration::from_secs(CHECK_INTERVAL_SECS)).await;
+        }
+    });
+
+    info!("Waitlist job spawned as background task");
+}
+
+/// Waitlist service
+pub struct WaitlistService {
+    pool: PgPool,
+    encryption_key: EncryptionKey,
+}
+
+impl WaitlistService {
+    pub fn new(pool: PgPool, encryption_key: EncryptionKey) -> Self {
+        Self {
+            pool,
+            encryption_key,
+        }
+    }
+
+    /// Waitlist settings (`waitlist.*`); defaults for missing or invalid
+    /// values
+    pub async fn settings(settings_service: &SettingsService) -> WaitlistSettings {
+        let defaults = WaitlistSettings::default();
+
+        WaitlistSettings {
+            offer_expiry_minutes: settings_service
+                .get_setting_value::<i64>(WAITLIST_OFFER_EXPIRY_SETTING_KEY)
+                .await
+                .ok()
+                .flatten()
+                .filter(|minutes| *minutes > 0)
+                .unwrap_or(defaults.offer_expiry_minutes),
+            offers_per_round: settings_service
+                .get_setting_value::<i64>(WAITLIST_OFFERS_PER_ROUND_SETTING_KEY)
+                .await
+                .ok()
+                .flatten()
+                .filter(|count| (1..=MAX_WAITLIST_OFFERS_PER_ROUND).contains(count))
+                .unwrap_or(defaults.offers_per_round),
+        }
+    }
+
+    // =========================================================================
+    // Entries
+    // =========================================================================
+
+    /// Waitlist entries, longest waiting first
+    pub async fn list(&self, query: &WaitlistQuery, user_id: Uuid) -> Result<Vec<WaitlistEntry>> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        let entries = sqlx::query_as::<_, WaitlistEntry>(&format!(
+            r#"
+            SELECT {}
+            FROM waitlist_entries w
+            JOIN patients p ON p.id = w.patient_id
+            WHERE w.status = $1
+              AND ($2::UUID IS NULL OR w.provider_id IS NULL OR w.provider_id = $2)
+              AND ($3::UUID IS NULL OR w.patient_id = $3)
+            ORDER BY w.created_at
+            LIMIT $4
+            "#,
+            ENTRY_COLUMNS
+        ))
+        .bind(query.status.unwrap_or(WaitlistEntryStatus::Waiting))
+        .bind(query.provider_id)
+        .bind(query.patient_id)
+        .bind(MAX_ENTRIES_LISTED)
+        .fetch_all(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        Ok(entries)
+    }
+
+    /// Put a patient on the waitlist
+    ///
+    /// A patient waits once per provider (or once for any provider): a
+    /// second entry is a Conflict.
+    pub async fn create(
+        &self,
+        req: &CreateWaitlistEntryRequest,
+        user_id: Uuid,
+        now: DateTime<Utc>,
+    ) -> Result<WaitlistEntry> {
+        req.validate_dates(now.with_timezone(&Rome).date_naive())
+            .map_err(|message| AppError::BadRequest(message.to_string()))?;
+
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        let patient_active: bool = sqlx::query_scalar(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM patients
+                WHERE id = $1 AND status = 'ACTIVE'
+                  AND anonymized_at IS NULL AND merged_at IS NULL
+            )
+            "#,
+        )
+        .bind(req.patient_id)
+        .fetch_one(&mut *tx)
+        .await?;
+        if !patient_active {
+            return Err(AppError::NotFound(
+                "Patient not found or inactive".to_string(),
+            ));
+        }
+
+        if let Some(provider_id) = req.provider_id {
+            let provider_active: bool = sqlx::query_scalar(
+                "SELECT EXISTS(SELECT 1 FROM users WHERE id = $1 AND is_active = true)",
+            )
+            .bind(provider_id)
+            .fetch_one(&mut *tx)
+            .await?;
+            if !provider_active {
+                return Err(AppError::BadRequest(
+                    "Provider not found or inactive".to_string(),
+                ));
+            }
+        }
+
+        let already_waiting: bool = sqlx::query_scalar(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM waitlist_entries
+                WHERE patient_id = $1
+                  AND provider_id IS NOT DISTINCT FROM $2
+                  AND status = 'WAITING'
+            )
+            "#,
+        )
+        .bind(req.patient_id)
+        .bind(req.provider_id)
+        .fetch_one(&mut *tx)
+        .await?;
+        if already_waiting {
+            return Err(AppError::Conflict(
+                "The patient is already on the waitlist for this provider".to_string(),
+            ));
+        }
+
+        let id: Uuid = sqlx::query_scalar(
+            r#"
+            INSERT INTO waitlist_entries (
+                patient_id, provider_id, appointment_type, duration_minutes,
+                earliest_date, latest_date, created_by
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING id
+            "#,
+        )
+        .bind(req.patient_id)
+        .bind(req.provider_id)
+        .bind(req.appointment_type)
+        .bind(req.duration_minutes.unwrap_or(30))
+        .bind(req.earliest_date)
+        .bind(req.latest_date)
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let entry = fetch_entry(&mut tx, id).await?;
+        tx.commit().await?;
+
+        Ok(entry)
+    }
+
+    /// Take a patient off the waitlist
+    ///
+    /// A pending offer is superseded: its links no longer book. Entries no
+    /// longer waiting are a Conflict.
+    pub async fn cancel(
+        &self,
+        id: Uuid,
+        user_id: Uuid,
+        now: DateTime<Utc>,
+    ) -> Result<WaitlistEntry> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+
+        sqlx::query(
+            r#"
+            UPDATE waitlist_offers
+            SET status = 'SUPERSEDED'
+            WHERE waitlist_entry_id = $1 AND status = 'PENDING'
+            "#,
+        )
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+        let cancelled = sqlx::query(
+            r#"
+            UPDATE waitlist_entries
+            SET status = 'CANCELLED', closed_at = $2
+            WHERE id = $1 AND status = 'WAITING'
+            "#,
+        )
+        .bind(id)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        let entry = fetch_entry(&mut tx, id).await?;
+        if cancelled == 0 {
+            return Err(AppError::Conflict(format!(
+                "Waitlist entry with status {:?} cannot be cancelled",
+                entry.status
+            )));
+        }
+        tx.commit().await?;
+
+        Ok(entry)
+    }
+
+    /// Slots offered to a waitlist entry, latest first
+    pub async fn offers(&self, id: Uuid, user_id: Uuid) -> Result<Vec<WaitlistOffer>> {
+        let mut tx = begin_with_rls(&self.pool, user_id).await?;
+        fetch_entry(&mut tx, id).await?;
+
+        let offers = sqlx::query_as::<_, WaitlistOffer>(
+            r#"
+            SELECT o.id, o.waitlist_entry_id, o.patient_id, s.provider_id, s.slot_start,
+                   s.slot_end, o.status, o.expires_at, o.responded_at, o.appointment_id,
+                   o.notification_id, o.created_at
+            FROM waitlist_offers o
+            JOIN waitlist_slots s ON s.id = o.slot_id
+            WHERE o.waitlist_entry_id = $1
+            ORDER BY o.created_at DESC
+            "#,
+        )
+        .bind(id)
+        .fetch_all(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        Ok(offers)
+    }
+
+    // =========================================================================
+    // Offers
+    // =========================================================================
+
+    /// Close what has run out, then offer the open slots without a pending
+    /// offer to the next patients
+    ///
+    /// Returns how many offers were sent. Needs the public URL of the
+    /// practice for the links; without it nothing is offered.
+    pub async fn run(
+        &self,
+        notification_service: &NotificationService,
+        settings: &WaitlistSettings,
+    ) -> Result<usize> {
+        let now = Utc::now();
+        self.close_expired(now).await?;
+
+        let Some(base_url) = BrandingService::new(self.pool.clone())
+            .load_settings()
+            .await
+            .ok()
+            .and_then(|s| s.public_base_url)
+            .filter(|url| !url.trim().is_empty())
+        else {
+            return Ok(0);
+        };
+
+        let mut tx = self.pool.begin().await?;
+        apply_rls_context(&mut tx, SYSTEM_USER_ID, SYSTEM_ROLE).await?;
+        let slots = sqlx::query_as::<_, OpenSlot>(
+            r#"
+            SELECT s.id, s.provider_id, s.appointment_type, s.slot_start, s.slot_end,
+                   a.patient_id AS cancelled_patient_id
+            FROM waitlist_slots s
+            JOIN appointments a ON a.id = s.cancelled_appointment_id
+            WHERE s.status = 'OPEN'
+              AND NOT EXISTS (
+                  SELECT 1 FROM waitlist_offers o
+                  WHERE o.slot_id = s.id AND o.status = 'PENDING'
+              )
+            ORDER BY s.slot_start
+            LIMIT $1
+            "#,
+        )
+        .bind(SLOT_BATCH_SIZE)
+        .fetch_all(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        let mut sent = 0;
+        for slot in slots {
+            match self
+                .offer_slot(&slot, settings, &base_url, notification_service, now)
+                .await
+            {
+                Ok(count) => sent += count,
+                Err(e) => warn!("Failed to offer waitlist slot {}: {}", slot.id, e),
+            }
+        }
+
+        Ok(sent)
+    }
+
+    /// Expire entries past their last day, close slots that can no longer be
+    /// offered, and expire or supersede the offers left pending
+    ///
+    /// Slots are locked before offers, as when answering an offer.
+    async fn close_expired(&self, now: DateTime<Utc>) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        apply_rls_context(&mut tx, SYSTEM_USER_ID, SYSTEM_ROLE).await?;
+
+        sqlx::query(
+            r#"
+            UPDATE waitlist_slots s
+            SET status = 'CLOSED', closed_at = $1
+            WHERE s.status = 'OPEN'
+              AND (
+                  s.slot_start - make_interval(mins => $2) <= $1
+                  OR EXISTS (
+                      SELECT 1 FROM appointments a
+                      WHERE a.provider_id = s.provider_id
+                        AND a.status NOT IN ('CANCELLED', 'NO_SHOW')
+                        AND a.scheduled_start < s.slot_end
+                        AND a.scheduled_end > s.slot_start
+                  )
+              )
+            "#,
+        )
+        .bind(now)
+        .bind(WAITLIST_OFFER_CUTOFF_MINUTES as i32)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            UPDATE waitlist_offers o
+            SET status = CASE WHEN s.status = 'OPEN' THEN 'EXPIRED' ELSE 'SUPERSEDED' END
+            FROM waitlist_slots s
+            WHERE s.id = o.slot_id
+              AND o.status = 'PENDING'
+              AND (o.expires_at <= $1 OR s.status <> 'OPEN')
+            "#,
+        )
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            UPDATE waitlist_entries
+            SET status = 'EXPIRED', closed_at = $1
+            WHERE status = 'WAITING'
+              AND latest_date < ($1 AT TIME ZONE 'Europe/Rome')::DATE
+            "#,
+        )
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Offer a slot to the next matching patients; returns how many offers
+    /// were sent
+    async fn offer_slot(
+        &self,
+        slot: &OpenSlot,
+        settings: &WaitlistSettings,
+        base_url: &str,
+        notification_service: &NotificationService,
+        now: DateTime<Utc>,
+    ) -> Result<usize> {
+        let slot_date = slot.slot_start.with_timezone(&Rome).date_naive();
+        let slot_minutes = (slot.slot_end - slot.slot_start).num_minutes() as i32;
+        let expires_at = settings.offer_expires_at(now, slot.slot_start);
+
+        let mut tx = self.pool.begin().await?;
+        apply_rls_context(&mut tx, SYSTEM_USER_ID, SYSTEM_ROLE).await?;
+
+        // Waiting longest first; a patient answers one offer at a time and
+        // is offered a slot once
+        let candidates = sqlx::query_as::<_, Candidate>(
+            r#"
+            SELECT w.id AS entry_id, w.patient_id, p.first_name, p.last_name, p.email,
+                   pnp.email_address_override
+            FROM waitlist_entries w
+            JOIN patients p ON p.id = w.patient_id
+            LEFT JOIN patient_notification_preferences pnp ON pnp.patient_id = w.patient_id
+            WHERE w.status = 'WAITING'
+              AND (w.provider_id IS NULL OR w.provider_id = $2)
+              AND (w.appointment_type IS NULL OR w.appointment_type = $3)
+              AND w.duration_minutes <= $4
+              AND (w.earliest_date IS NULL OR w.earliest_date <= $5)
+              AND (w.latest_date IS NULL OR w.latest_date >= $5)
+              AND w.patient_id <> $6
+              AND p.status = 'ACTIVE' AND p.anonymized_at IS NULL AND p.merged_at IS NULL
+              AND (p.email IS NOT NULL OR pnp.email_address_override IS NOT NULL)
+              AND (pnp.email_enabled IS NULL OR pnp.email_enabled = true)
+              AND NOT EXISTS (
+                  SELECT 1 FROM waitlist_offers o
+                  WHERE o.waitlist_entry_id = w.id
+                    AND (o.slot_id = $1 OR o.status = 'PENDING')
+              )
+              AND NOT EXISTS (
+                  SELECT 1 FROM appointments a
+                  WHERE a.patient_id = w.patient_id
+                    AND a.status NOT IN ('CANCELLED', 'NO_SHOW')
+                    AND a.scheduled_start < $8
+                    AND a.scheduled_end > $7
+              )
+            ORDER BY w.created_at
+            LIMIT $9
+            "#,
+        )
+        .bind(slot.id)
+        .bind(slot.provider_id)
+        .bind(slot.appointment_type)
+        .bind(slot_minutes)
+        .bind(slot_date)
+        .bind(slot.cancelled_patient_id)
+        .bind(slot.slot_start)
+        .bind(slot.slot_end)
+        .bind(settings.offers_per_round)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let doctor_name: String = sqlx::query_scalar(
+            "SELECT 'Dr. ' || first_name || ' ' || last_name FROM users WHERE id = $1",
+        )
+        .bind(slot.provider_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let mut offers = Vec::new();
+        for candidate in candidates {
+            let decrypted = candidate
+                .email_address_override
+                .as_deref()
+                .or(candidate.email.as_deref())
+                .map(|email| -> anyhow::Result<(String, String)> {
+                    Ok((
+                        self.encryption_key.decrypt(email)?,
+                        format!(
+                            "{} {}",
+                            self.encryption_key.decrypt(&candidate.first_name)?,
+                            self.encryption_key.decrypt(&candidate.last_name)?
+                        ),
+                    ))
+                });
+            let (email, patient_name) = match decrypted {
+                Some(Ok(recipient)) => recipient,
+                Some(Err(e)) => {
+                    warn!(
+                        "Skipping waitlist entry {} - failed to decrypt patient details: {}",
+                        candidate.entry_id, e
+                    );
+                    continue;
+                }
+                None => continue,
+            };
+
+            let offer_id: Uuid = sqlx::query_scalar(
+                r#"
+                INSERT INTO waitlist_offers (slot_id, waitlist_entry_id, patient_id, expires_at)
+                VALUES ($1, $2, $3, $4)
+                RETURNING id
+                "#,
+            )
+            .bind(slot.id)
+            .bind(candidate.entry_id)
+            .bind(candidate.patient_id)
+            .bind(expires_at)
+            .fetch_one(&mut *tx)
+            .await?;
+
+            offers.push((offer_id, candidate.patient_id, email, patient_name));
+        }
+        tx.commit().await?;
+
+        // Emails go out once the offers exist, so that a link can never
+        // point to an offer that was rolled back
+        let mut sent = 0;
+        for (offer_id, patient_id, email, patient_name) in offers {
+            let links = NotificationService::waitlist_offer_links(
+                &self.encryption_key,
+                base_url,
+                offer_id,
+                patient_id,
+                expires_at,
+            );
+            let queued = notification_service
+                .queue_waitlist_offer(
+                    patient_id,
+                    &email,
+                    &patient_name,
+                    slot.slot_start,
+                    &doctor_name,
+                    &links,
+                    expires_at,
+                    SYSTEM_USER_ID,
+                )
+                .await;
+
+            let mut tx = self.pool.begin().await?;
+            apply_rls_context(&mut tx, SYSTEM_USER_ID, SYSTEM_ROLE).await?;
+            match queued {
+                Ok(notification) => {
+                    sqlx::query("UPDATE waitlist_offers SET notification_id = $2 WHERE id = $1")
+                        .bind(offer_id)
+                        .bind(notification.id)
+                        .execute(&mut *tx)
+                        .await?;
+                    sent += 1;
+                }
+                Err(e) => {
+                    // An offer the patient never received goes to the next
+                    // patient
+                    warn!("Failed to queue waitlist offer {}: {}", offer_id, e);
+                    sqlx::query(
+                        r#"
+                        UPDATE waitlist_offers
+                        SET status = 'EXPIRED'
+                        WHERE id = $1 AND status = 'PENDING'
+                        "#,
+                    )
+                    .bind(offer_id)
+                    .execute(&mut *tx)
+                    .await?;
+                }
+            }
+            tx.commit().await?;
+        }
+
+        Ok(sent)
+    }
+
+    /// Accept or decline an offer from its link, as the system user
+    ///
+    /// The slot is locked first, so of two patients accepting at once the
+    /// second finds it filled and its offer superseded. Accepting books the
+    /// appointment for the length the patient needs, from the start of the
+    /// slot; when that fails (for example the time was booked meanwhile)
+    /// the slot is closed. Fails with "not found" for another patient's
+    /// offer.
+    pub async fn respond(
+        &self,
+        claims: &WaitlistOfferClaims,
+        now: DateTime<Utc>,
+    ) -> Result<WaitlistOfferOutcome> {
+        let mut tx = self.pool.begin().await?;
+        apply_rls_context(&mut tx, SYSTEM_USER_ID, SYSTEM_ROLE).await?;
+
+        let not_found = || AppError::NotFound("This link is invalid or has expired".to_string());
+
+        let slot_id: Uuid = sqlx::query_scalar(
+            "SELECT slot_id FROM waitlist_offers WHERE id = $1 AND patient_id = $2",
+        )
+        .bind(claims.offer_id)
+        .bind(claims.patient_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(not_found)?;
+        sqlx::query("SELECT id FROM waitlist_slots WHERE id = $1 FOR UPDATE")
+            .bind(slot_id)
+            .execute(&mut *tx)
+            .await?;
+
+        let offer = sqlx::query_as::<_, OfferForUpdate>(
+            r#"
+            SELECT o.id, o.slot_id, o.waitlist_entry_id, o.status, o.appointment_id,
+                   s.provider_id, s.appointment_type AS slot_type, s.slot_start, s.slot_end,
+                   s.status AS slot_status, w.appointment_type AS requested_type,
+                   w.duration_minutes, w.status AS entry_status, w.created_by AS entry_created_by
+            FROM waitlist_offers o
+            JOIN waitlist_slots s ON s.id = o.slot_id
+            JOIN waitlist_entries w ON w.id = o.waitlist_entry_id
+            WHERE o.id = $1
+            FOR UPDATE OF o
+            "#,
+        )
+        .bind(claims.offer_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let provider_name: String = sqlx::query_scalar(
+            "SELECT 'Dr. ' || first_name || ' ' || last_name FROM users WHERE id = $1",
+        )
+        .bind(offer.provider_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let outcome = |status, appointment_id, changed| WaitlistOfferOutcome {
+            offer_id: offer.id,
+            status,
+            slot_start: offer.slot_start,
+            slot_end: offer.slot_end,
+            provider_name: provider_name.clone(),
+            appointment_id,
+            changed,
+        };
+
+        // Answered, expired or superseded already: the link changes nothing
+        if offer.status != WaitlistOfferStatus::Pending {
+            return Ok(outcome(offer.status, offer.appointment_id, false));
+        }
+
+        if claims.action == WaitlistOfferAction::Decline {
+            sqlx::query(
+                "UPDATE waitlist_offers SET status = 'DECLINED', responded_at = $2 WHERE id = $1",
+            )
+            .bind(offer.id)
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+            tx.commit().await?;
+
+            return Ok(outcome(WaitlistOfferStatus::Declined, None, true));
+        }
+
+        if offer.slot_status != "OPEN" || offer.entry_status != WaitlistEntryStatus::Waiting {
+            supersede_offer(&mut tx, offer.id).await?;
+            tx.commit().await?;
+
+            return Ok(outcome(WaitlistOfferStatus::Superseded, None, false));
+        }
+
+        let request = CreateAppointmentRequest {
+            patient_id: claims.patient_id.to_string(),
+            provider_id: offer.provider_id.to_string(),
+            scheduled_start: offer.slot_start,
+            duration_minutes: offer.duration_minutes,
+            appointment_type: offer.requested_type.unwrap_or(offer.slot_type),
+            reason: None,
+            notes: None,
+            is_recurring: None,
+            recurring_pattern: None,
+            send_notification: None,
+            overbook: false,
+            resource_ids: Vec::new(),
+        };
+
+        // In a savepoint: a failed booking must not undo closing the slot
+        let mut savepoint = tx.begin().await?;
+        let booked = AppointmentService::new(self.pool.clone(), self.encryption_key.clone())
+            .create_appointment_in_tx(&mut savepoint, request, offer.entry_created_by)
+            .await;
+
+        let appointment = match booked {
+            Ok((appointment, _)) => {
+                savepoint.commit().await?;
+                appointment
+            }
+            Err(e) => {
+                savepoint.rollback().await?;
+                warn!(
+                    "Waitlist slot {} could not be booked: {:#}",
+                    offer.slot_id, e
+                );
+
+                sqlx::query(
+                    "UPDATE waitlist_slots SET status = 'CLOSED', closed_at = $2 WHERE id = $1",
+                )
+                .bind(offer.slot_id)
+                .bind(now)
+                .execute(&mut *tx)
+                .await?;
+                sqlx::query(
+                    r#"
+                    UPDATE waitlist_offers
+                    SET status = 'SUPERSEDED'
+                    WHERE slot_id = $1 AND status = 'PENDING'
+                    "#,
+                )
+                .bind(offer.slot_id)
+                .execute(&mut *tx)
+                .await?;
+                tx.commit().await?;
+
+                return Ok(outcome(WaitlistOfferStatus::Superseded, None, false));
+            }
+        };
+
+        sqlx::query(
+            r#"
+            UPDATE waitlist_offers
+            SET status = 'ACCEPTED', responded_at = $2, appointment_id = $3
+            WHERE id = $1
+            "#,
+        )
+        .bind(offer.id)
+        .bind(now)
+        .bind(appointment.id)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            r#"
+            UPDATE waitlist_offers
+            SET status = 'SUPERSEDED'
+            WHERE slot_id = $1 AND status = 'PENDING'
+            "#,
+        )
+        .bind(offer.slot_id)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            r#"
+            UPDATE waitlist_slots
+            SET status = 'FILLED', filled_appointment_id = $2, closed_at = $3
+            WHERE id = $1
+            "#,
+        )
+        .bind(offer.slot_id)
+        .bind(appointment.id)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            r#"
+            UPDATE waitlist_entries
+            SET status = 'BOOKED', booked_appointment_id = $2, closed_at = $3
+            WHERE id = $1
+            "#,
+        )
+        .bind(offer.waitlist_entry_id)
+        .bind(appointment.id)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(outcome(
+            WaitlistOfferStatus::Accepted,
+            Some(appointment.id),
+            true,
+        ))
+    }
+}
+
+/// Waitlist entry visible in the transaction
+async fn fetch_entry(tx: &mut PgConnection, id: Uuid) -> Result<WaitlistEntry> {
+    sqlx::query_as::<_, WaitlistEntry>(&format!(
+        r#"
+        SELECT {}
+        FROM waitlist_entries w
+        JOIN patients p ON p.id = w.patient_id
+        WHERE w.id = $1
+        "#,
+        ENTRY_COLUMNS
+    ))
+    .bind(id)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Waitlist entry not found".to_string()))
+}
+
+/// Mark an offer superseded
+async fn supersede_offer(tx: &mut PgConnection, id: Uuid) -> Result<()> {
+    sqlx::query("UPDATE waitlist_offers SET status = 'SUPERSEDED' WHERE id = $1")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    Ok(())
+}
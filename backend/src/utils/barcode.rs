@@ -0,0 +1,160 @@
+/*!
+ * Barcode Utilities
+ *
+ * Code 39 barcodes rendered as SVG, for numbers printed on documents (lab
+ * requisitions). Code 39 needs no check digit and is read by every lab
+ * scanner; it covers uppercase letters, digits and `-. $/+%`.
+ */
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+
+/// Width of a narrow element, in SVG units
+const NARROW: u32 = 2;
+/// Width of a wide element (2.5 x narrow)
+const WIDE: u32 = 5;
+/// Bar height, in SVG units
+const HEIGHT: u32 = 50;
+/// Quiet zone on each side (10 x narrow)
+const QUIET_ZONE: u32 = 10 * NARROW;
+
+/// Element widths of each character: 5 bars and 4 spaces, alternating from
+/// a bar; `1` is a wide element (exactly 3 per character)
+const PATTERNS: &[(char, &str)] = &[
+    ('0', "000110100"),
+    ('1', "100100001"),
+    ('2', "001100001"),
+    ('3', "101100000"),
+    ('4', "000110001"),
+    ('5', "100110000"),
+    ('6', "001110000"),
+    ('7', "000100101"),
+    ('8', "100100100"),
+    ('9', "001100100"),
+    ('A', "100001001"),
+    ('B', "001001001"),
+    ('C', "101001000"),
+    ('D', "000011001"),
+    ('E', "100011000"),
+    ('F', "001011000"),
+    ('G', "000001101"),
+    ('H', "100001100"),
+    ('I', "001001100"),
+    ('J', "000011100"),
+    ('K', "100000011"),
+    ('L', "001000011"),
+    ('M', "101000010"),
+    ('N', "000010011"),
+    ('O', "100010010"),
+    ('P', "001010010"),
+    ('Q', "000000111"),
+    ('R', "100000110"),
+    ('S', "001000110"),
+    ('T', "000010110"),
+    ('U', "110000001"),
+    ('V', "011000001"),
+    ('W', "111000000"),
+    ('X', "010010001"),
+    ('Y', "110010000"),
+    ('Z', "011010000"),
+    ('-', "010000101"),
+    ('.', "110000100"),
+    (' ', "011000100"),
+    ('$', "010101000"),
+    ('/', "010100010"),
+    ('+', "010001010"),
+    ('%', "000101010"),
+    ('*', "010010100"),
+];
+
+fn pattern(c: char) -> Option<&'static str> {
+    PATTERNS
+        .iter()
+        .find(|(p, _)| *p == c)
+        .map(|(_, pattern)| *pattern)
+}
+
+/// Bars of a Code 39 barcode as (x, width) pairs, start and stop characters
+/// included
+///
+/// Returns None if the value is empty or has a character Code 39 cannot
+/// encode (`*` is reserved for start/stop).
+pub fn code39_bars(value: &str) -> Option<Vec<(u32, u32)>> {
+    if value.is_empty() || value.contains('*') {
+        return None;
+    }
+
+    let mut bars = Vec::new();
+    let mut x = QUIET_ZONE;
+    for c in std::iter::once('*')
+        .chain(value.chars())
+        .chain(std::iter::once('*'))
+    {
+        for (i, element) in pattern(c)?.chars().enumerate() {
+            let width = if element == '1' { WIDE } else { NARROW };
+            if i % 2 == 0 {
+                bars.push((x, width));
+            }
+            x += width;
+        }
+        // Narrow gap between characters
+        x += NARROW;
+    }
+
+    Some(bars)
+}
+
+/// Code 39 barcode as an SVG document
+pub fn code39_svg(value: &str) -> Option<String> {
+    let bars = code39_bars(value)?;
+    let width = bars.last().map(|(x, w)| x + w + QUIET_ZONE)?;
+
+    let rects: String = bars
+        .iter()
+        .map(|(x, w)| format!(r#"<rect x="{}" y="0" width="{}" height="{}"/>"#, x, w, HEIGHT))
+        .collect();
+
+    Some(format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {w} {h}" width="{w}" height="{h}"><rect width="{w}" height="{h}" fill="white"/><g fill="black">{rects}</g></svg>"#,
+        w = width,
+        h = HEIGHT,
+        rects = rects
+    ))
+}
+
+/// Code 39 barcode as an SVG data URI, for `<img src>` in templates
+pub fn code39_data_uri(value: &str) -> Option<String> {
+    code39_svg(value).map(|svg| format!("data:image/svg+xml;base64,{}", BASE64.encode(svg)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_patterns_have_three_wide_elements() {
+        for (c, pattern) in PATTERNS {
+            assert_eq!(pattern.len(), 9, "pattern of {:?}", c);
+            assert_eq!(pattern.matches('1').count(), 3, "pattern of {:?}", c);
+        }
+    }
+
+    #[test]
+    fn test_code39_bars() {
+        // Start, one character, stop: 5 bars each
+        let bars = code39_bars("A").unwrap();
+        assert_eq!(bars.len(), 15);
+        assert_eq!(bars[0], (QUIET_ZONE, NARROW));
+
+        assert!(code39_bars("LAB-2026-000123").is_some());
+        assert!(code39_bars("").is_none());
+        assert!(code39_bars("lab").is_none());
+        assert!(code39_bars("A*B").is_none());
+    }
+
+    #[test]
+    fn test_code39_data_uri() {
+        let uri = code39_data_uri("LAB-2026-000001").unwrap();
+        assert!(uri.starts_with("data:image/svg+xml;base64,"));
+        assert!(code39_data_uri("lab#1").is_none());
+    }
+}
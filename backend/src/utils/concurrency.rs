@@ -0,0 +1,142 @@
+/*!
+ * Optimistic Concurrency
+ *
+ * Records several users edit at once (patients, visits, appointments) are
+ * versioned by their `updated_at`. An update may carry the version the
+ * client read, as an `If-Match` header or an `expected_version` body field;
+ * if the record changed since, the update is refused with 409 and the
+ * current server state instead of silently overwriting the other edit.
+ * Updates without a precondition behave as before.
+ */
+
+use axum::http::{header, HeaderMap};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use crate::utils::{AppError, Result};
+
+/// Version of a record as an entity tag (the quoted `updated_at`), for the
+/// `ETag` response header
+pub fn version_tag(updated_at: DateTime<Utc>) -> String {
+    format!(
+        "\"{}\"",
+        updated_at.to_rfc3339_opts(SecondsFormat::Micros, true)
+    )
+}
+
+/// Version the client expects to update, from `If-Match` or the body's
+/// `expected_version`
+///
+/// `If-Match: *` is no precondition. Both may be sent if they agree.
+pub fn expected_version(
+    headers: &HeaderMap,
+    body: Option<DateTime<Utc>>,
+) -> Result<Option<DateTime<Utc>>> {
+    let Some(value) = headers.get(header::IF_MATCH) else {
+        return Ok(body);
+    };
+
+    let value = value
+        .to_str()
+        .map_err(|_| AppError::BadRequest("Invalid If-Match header".to_string()))?
+        .trim();
+    if value == "*" {
+        return Ok(body);
+    }
+
+    let tag = value.trim_start_matches("W/").trim_matches('"');
+    let version = DateTime::parse_from_rfc3339(tag)
+        .map(|v| v.with_timezone(&Utc))
+        .map_err(|_| {
+            AppError::BadRequest("If-Match must be the record version (its updated_at)".to_string())
+        })?;
+
+    match body {
+        Some(body) if body != version => Err(AppError::BadRequest(
+            "If-Match and expected_version differ".to_string(),
+        )),
+        _ => Ok(Some(version)),
+    }
+}
+
+/// Whether the record changed since the expected version
+pub fn is_stale(expected: Option<DateTime<Utc>>, updated_at: DateTime<Utc>) -> bool {
+    expected.is_some_and(|expected| expected != updated_at)
+}
+
+/// 409 carrying the record as it is now
+pub fn version_conflict<T: Serialize>(current: &T) -> AppError {
+    AppError::VersionConflict(serde_json::to_value(current).unwrap_or_default())
+}
+
+/// Lock a row until the end of the transaction, so the version check and
+/// the update cannot interleave with another update
+///
+/// `table` is always a literal from the caller, never user input.
+pub async fn lock_row(conn: &mut PgConnection, table: &'static str, id: Uuid) -> Result<()> {
+    sqlx::query(&format!("SELECT 1 FROM {} WHERE id = $1 FOR UPDATE", table))
+        .bind(id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+    use chrono::TimeZone;
+
+    fn version() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, 2, 9, 30, 0).unwrap() + chrono::Duration::microseconds(123456)
+    }
+
+    #[test]
+    fn test_expected_version_from_if_match() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::IF_MATCH,
+            HeaderValue::from_str(&version_tag(version())).unwrap(),
+        );
+        assert_eq!(expected_version(&headers, None).unwrap(), Some(version()));
+        assert_eq!(
+            expected_version(&headers, Some(version())).unwrap(),
+            Some(version())
+        );
+        assert!(expected_version(&headers, Some(Utc::now())).is_err());
+
+        headers.insert(
+            header::IF_MATCH,
+            HeaderValue::from_static("W/\"2026-03-02T09:30:00.123456+00:00\""),
+        );
+        assert_eq!(expected_version(&headers, None).unwrap(), Some(version()));
+
+        headers.insert(header::IF_MATCH, HeaderValue::from_static("\"v1\""));
+        assert!(expected_version(&headers, None).is_err());
+    }
+
+    #[test]
+    fn test_expected_version_without_precondition() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(expected_version(&headers, None).unwrap(), None);
+        assert_eq!(
+            expected_version(&headers, Some(version())).unwrap(),
+            Some(version())
+        );
+
+        headers.insert(header::IF_MATCH, HeaderValue::from_static("*"));
+        assert_eq!(expected_version(&headers, None).unwrap(), None);
+    }
+
+    #[test]
+    fn test_is_stale() {
+        assert!(!is_stale(None, version()));
+        assert!(!is_stale(Some(version()), version()));
+        assert!(is_stale(
+            Some(version() - chrono::Duration::seconds(1)),
+            version()
+        ));
+    }
+}
@@ -1,24 +1,85 @@
 // Encryption utilities for PHI/PII data using AES-256-GCM
 // All medical data marked with 🔒 must be encrypted before storage
+// Lookup fields can instead be encrypted deterministically (AES-SIV)
 
 use aes_gcm::{
     aead::{Aead, KeyInit, OsRng},
     Aes256Gcm, Nonce,
 };
+use aes_siv::siv::Aes256Siv;
 use anyhow::{Context, Result};
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use hmac::{Hmac, Mac};
 use rand::RngCore;
 use serde::{Deserialize, Serialize};
+use sha2::Sha256;
 use std::env;
 
+type HmacSha256 = Hmac<Sha256>;
+
 /// Size of the nonce for AES-GCM (96 bits / 12 bytes)
 const NONCE_SIZE: usize = 12;
 
+/// Label the blind index key is derived with, so it differs from the
+/// encryption key itself
+const BLIND_INDEX_KEY_LABEL: &[u8] = b"docpat blind index v1";
+
+/// Label the key of signed values (e.g. links emailed to patients) is
+/// derived with
+const SIGNING_KEY_LABEL: &[u8] = b"docpat signing v1";
+
+/// Labels the two halves of the AES-SIV key are derived with
+const DETERMINISTIC_MAC_KEY_LABEL: &[u8] = b"docpat deterministic v1 mac";
+const DETERMINISTIC_ENC_KEY_LABEL: &[u8] = b"docpat deterministic v1 enc";
+
+/// Length of trigram blind indexes (64 bits of the HMAC)
+const TRIGRAM_INDEX_HEX_CHARS: usize = 16;
+
+/// Prefix of deterministic ciphertexts: `siv:<field>:<base64>`
+/// (randomized ciphertexts are plain base64, which has no `:`)
+const DETERMINISTIC_PREFIX: &str = "siv:";
+
+/// Fields encrypted deterministically, so that equal values have equal
+/// ciphertexts (uniqueness constraints, joins, equality lookups)
+///
+/// Deterministic encryption reveals which rows share a value, so it is
+/// kept to identifiers that are looked up or must be unique. The field is
+/// authenticated with the value: equal values of different fields do not
+/// match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeterministicField {
+    FiscalCode,
+    PolicyNumber,
+}
+
+impl DeterministicField {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::FiscalCode => "fiscal_code",
+            Self::PolicyNumber => "policy_number",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "fiscal_code" => Some(Self::FiscalCode),
+            "policy_number" => Some(Self::PolicyNumber),
+            _ => None,
+        }
+    }
+}
+
 /// Encryption key loaded from environment variable
 /// Must be 32 bytes (256 bits) for AES-256
 #[derive(Clone)]
 pub struct EncryptionKey {
     cipher: Aes256Gcm,
+    /// HMAC keyed with a key derived from the encryption key
+    blind_index_mac: HmacSha256,
+    /// AES-SIV key (MAC and encryption halves) derived from the encryption key
+    deterministic_key: [u8; 64],
+    /// HMAC for signed values, keyed with another derived key
+    signing_mac: HmacSha256,
 }
 
 impl EncryptionKey {
@@ -28,6 +89,12 @@ impl EncryptionKey {
         let key_base64 = env::var("ENCRYPTION_KEY")
             .context("ENCRYPTION_KEY environment variable not set")?;
 
+        Self::from_base64(&key_base64)
+    }
+
+    /// Initialize encryption key from its base64 encoding (32 bytes), e.g.
+    /// `SecurityConfig::encryption_key`
+    pub fn from_base64(key_base64: &str) -> Result<Self> {
         let key_bytes = BASE64
             .decode(key_base64)
             .context("Failed to decode ENCRYPTION_KEY from base64")?;
@@ -39,7 +106,99 @@ impl EncryptionKey {
         let cipher = Aes256Gcm::new_from_slice(&key_bytes)
             .context("Failed to create cipher from encryption key")?;
 
-        Ok(Self { cipher })
+        let blind_index_mac =
+            <HmacSha256 as KeyInit>::new_from_slice(&derive_key(&key_bytes, BLIND_INDEX_KEY_LABEL)?)
+                .context("Failed to derive blind index key")?;
+
+        let mut deterministic_key = [0u8; 64];
+        deterministic_key[..32].copy_from_slice(&derive_key(&key_bytes, DETERMINISTIC_MAC_KEY_LABEL)?);
+        deterministic_key[32..].copy_from_slice(&derive_key(&key_bytes, DETERMINISTIC_ENC_KEY_LABEL)?);
+
+        let signing_mac =
+            <HmacSha256 as KeyInit>::new_from_slice(&derive_key(&key_bytes, SIGNING_KEY_LABEL)?)
+                .context("Failed to derive signing key")?;
+
+        Ok(Self {
+            cipher,
+            blind_index_mac,
+            deterministic_key,
+            signing_mac,
+        })
+    }
+
+    /// Keyed hash (HMAC-SHA256, hex) of a value, to find encrypted data
+    /// without decrypting it
+    ///
+    /// Equal values give equal hashes, so callers normalize the value first
+    /// and prefix it with what it is, keeping hashes of different fields
+    /// apart.
+    pub fn blind_index(&self, value: &str) -> String {
+        let mut mac = self.blind_index_mac.clone();
+        mac.update(value.as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    /// Blind index of the value of a field (`field` keeps the hashes of
+    /// different fields apart)
+    pub fn field_blind_index(&self, field: &str, value: &str) -> String {
+        self.blind_index(&format!("{}:{}", field, value))
+    }
+
+    /// Signature (HMAC-SHA256, hex) of a value handed out and read back
+    /// later, e.g. in a link; `purpose` keeps signatures for different uses
+    /// apart
+    pub fn sign(&self, purpose: &str, value: &str) -> String {
+        let mut mac = self.signing_mac.clone();
+        mac.update(format!("{}:{}", purpose, value).as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    /// Whether `signature` is the signature of a value (constant time)
+    pub fn verify_signature(&self, purpose: &str, value: &str, signature: &str) -> bool {
+        let Ok(signature) = hex::decode(signature) else {
+            return false;
+        };
+        let mut mac = self.signing_mac.clone();
+        mac.update(format!("{}:{}", purpose, value).as_bytes());
+        mac.verify_slice(&signature).is_ok()
+    }
+
+    /// Blind indexes of the prefixes of a value, from `min_chars` up to
+    /// `max_chars` characters, for prefix lookups: a value starts with
+    /// `p` when its prefixes include `field_blind_index(field, p)`
+    pub fn prefix_blind_indexes(
+        &self,
+        field: &str,
+        value: &str,
+        min_chars: usize,
+        max_chars: usize,
+    ) -> Vec<String> {
+        value
+            .char_indices()
+            .map(|(i, c)| i + c.len_utf8())
+            .enumerate()
+            .filter(|(n, _)| (min_chars..=max_chars).contains(&(n + 1)))
+            .map(|(_, end)| self.field_blind_index(field, &value[..end]))
+            .collect()
+    }
+
+    /// Blind indexes of the trigrams (runs of three characters) of a value,
+    /// for substring lookups: a value contains `s` when its trigrams include
+    /// every trigram of `s`. Hashes are cut to 16 hex characters; the rare
+    /// collisions are false positives that callers check after decryption.
+    pub fn trigram_blind_indexes(&self, field: &str, value: &str) -> Vec<String> {
+        let chars: Vec<char> = value.chars().collect();
+        let mut indexes: Vec<String> = chars
+            .windows(3)
+            .map(|trigram| {
+                let mut index = self.field_blind_index(field, &trigram.iter().collect::<String>());
+                index.truncate(TRIGRAM_INDEX_HEX_CHARS);
+                index
+            })
+            .collect();
+        indexes.sort();
+        indexes.dedup();
+        indexes
     }
 
     /// Encrypt plaintext data
@@ -63,9 +222,72 @@ impl EncryptionKey {
         Ok(BASE64.encode(combined))
     }
 
+    /// Encrypt plaintext deterministically (AES-SIV)
+    /// Returns `siv:<field>:<base64>`; the same value of the same field always
+    /// gives the same string. Callers normalize the value first.
+    pub fn encrypt_deterministic(&self, field: DeterministicField, plaintext: &str) -> Result<String> {
+        let ciphertext = self
+            .deterministic_cipher()?
+            .encrypt([field.as_str().as_bytes()], plaintext.as_bytes())
+            .map_err(|e| anyhow::anyhow!("Deterministic encryption failed: {}", e))?;
+
+        Ok(format!(
+            "{}{}:{}",
+            DETERMINISTIC_PREFIX,
+            field.as_str(),
+            BASE64.encode(ciphertext)
+        ))
+    }
+
+    /// Encrypt optional string field deterministically
+    pub fn encrypt_deterministic_optional(
+        &self,
+        field: DeterministicField,
+        plaintext: &Option<String>,
+    ) -> Result<Option<String>> {
+        match plaintext {
+            Some(text) => Ok(Some(self.encrypt_deterministic(field, text)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Whether a ciphertext was produced by `encrypt_deterministic`
+    pub fn is_deterministic(encrypted: &str) -> bool {
+        encrypted.starts_with(DETERMINISTIC_PREFIX)
+    }
+
+    fn decrypt_deterministic(&self, encrypted: &str) -> Result<String> {
+        let (field, encoded) = encrypted
+            .strip_prefix(DETERMINISTIC_PREFIX)
+            .and_then(|rest| rest.split_once(':'))
+            .context("Malformed deterministic ciphertext")?;
+        let field = DeterministicField::from_str(field)
+            .with_context(|| format!("Unknown deterministic field: {}", field))?;
+        let ciphertext = BASE64
+            .decode(encoded)
+            .context("Failed to decode encrypted data from base64")?;
+
+        let plaintext_bytes = self
+            .deterministic_cipher()?
+            .decrypt([field.as_str().as_bytes()], &ciphertext)
+            .map_err(|e| anyhow::anyhow!("Decryption failed: {}", e))?;
+
+        String::from_utf8(plaintext_bytes).context("Decrypted data is not valid UTF-8")
+    }
+
+    fn deterministic_cipher(&self) -> Result<Aes256Siv> {
+        Aes256Siv::new_from_slice(&self.deterministic_key)
+            .map_err(|e| anyhow::anyhow!("Invalid deterministic encryption key: {}", e))
+    }
+
     /// Decrypt ciphertext
-    /// Expects base64-encoded string in format: nonce||ciphertext
+    /// Expects base64-encoded string in format: nonce||ciphertext, or a
+    /// deterministic ciphertext from `encrypt_deterministic`
     pub fn decrypt(&self, encrypted_base64: &str) -> Result<String> {
+        if Self::is_deterministic(encrypted_base64) {
+            return self.decrypt_deterministic(encrypted_base64);
+        }
+
         // Decode from base64
         let combined = BASE64
             .decode(encrypted_base64)
@@ -88,6 +310,38 @@ impl EncryptionKey {
         String::from_utf8(plaintext_bytes).context("Decrypted data is not valid UTF-8")
     }
 
+    /// Encrypt binary data (file contents)
+    /// Returns raw bytes in format: nonce||ciphertext
+    pub fn encrypt_bytes(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let mut nonce_bytes = [0u8; NONCE_SIZE];
+        OsRng.fill_bytes(&mut nonce_bytes);
+        let nonce = Nonce::from_slice(&nonce_bytes);
+
+        let ciphertext = self
+            .cipher
+            .encrypt(nonce, plaintext)
+            .map_err(|e| anyhow::anyhow!("Encryption failed: {}", e))?;
+
+        let mut combined = nonce_bytes.to_vec();
+        combined.extend_from_slice(&ciphertext);
+
+        Ok(combined)
+    }
+
+    /// Decrypt binary data produced by `encrypt_bytes`
+    pub fn decrypt_bytes(&self, encrypted: &[u8]) -> Result<Vec<u8>> {
+        if encrypted.len() < NONCE_SIZE {
+            anyhow::bail!("Encrypted data is too short");
+        }
+
+        let (nonce_bytes, ciphertext) = encrypted.split_at(NONCE_SIZE);
+        let nonce = Nonce::from_slice(nonce_bytes);
+
+        self.cipher
+            .decrypt(nonce, ciphertext)
+            .map_err(|e| anyhow::anyhow!("Decryption failed: {}", e))
+    }
+
     /// Encrypt optional string field
     pub fn encrypt_optional(&self, plaintext: &Option<String>) -> Result<Option<String>> {
         match plaintext {
@@ -135,6 +389,18 @@ impl EncryptionKey {
     }
 }
 
+/// Key derived from the encryption key for another purpose:
+/// HMAC-SHA256(encryption key, label)
+fn derive_key(key_bytes: &[u8], label: &[u8]) -> Result<[u8; 32]> {
+    let mut derive = <HmacSha256 as KeyInit>::new_from_slice(key_bytes)
+        .context("Failed to derive key from encryption key")?;
+    derive.update(label);
+
+    let mut key = [0u8; 32];
+    key.copy_from_slice(&derive.finalize().into_bytes());
+    Ok(key)
+}
+
 /// Generate a new random encryption key
 /// Used for initial setup or key rotation
 pub fn generate_encryption_key() -> String {
@@ -174,6 +440,20 @@ mod tests {
         assert_eq!(decrypted, plaintext);
     }
 
+    #[test]
+    fn test_encrypt_decrypt_bytes() {
+        let key = setup_test_key();
+        let content = [0x89, 0x50, 0x4E, 0x47, 0x00, 0xFF, 0x00];
+
+        let encrypted = key.encrypt_bytes(&content).unwrap();
+        assert_ne!(&encrypted[NONCE_SIZE..], &content[..]);
+
+        let decrypted = key.decrypt_bytes(&encrypted).unwrap();
+        assert_eq!(decrypted, content);
+
+        assert!(key.decrypt_bytes(&encrypted[..NONCE_SIZE - 1]).is_err());
+    }
+
     #[test]
     fn test_encrypt_decrypt_optional() {
         let key = setup_test_key();
@@ -237,6 +517,91 @@ mod tests {
         assert_eq!(BASE64.decode(&key2).unwrap().len(), 32);
     }
 
+    #[test]
+    fn test_blind_index() {
+        let key = setup_test_key();
+
+        let index = key.blind_index("visit-notes:carpal");
+        assert_eq!(index.len(), 64);
+        assert_eq!(index, key.blind_index("visit-notes:carpal"));
+        assert_ne!(index, key.blind_index("visit-notes:tunnel"));
+
+        // Derived from the encryption key, not a bare hash of the value
+        use sha2::Digest;
+        assert_ne!(index, hex::encode(Sha256::digest("visit-notes:carpal")));
+    }
+
+    #[test]
+    fn test_signature() {
+        let key = setup_test_key();
+
+        let signature = key.sign("appointment-link", "abc.confirm");
+        assert!(key.verify_signature("appointment-link", "abc.confirm", &signature));
+        assert!(!key.verify_signature("appointment-link", "abc.cancel", &signature));
+        assert!(!key.verify_signature("other", "abc.confirm", &signature));
+        assert!(!key.verify_signature("appointment-link", "abc.confirm", "not hex"));
+
+        // Not the blind index of the same value
+        assert_ne!(signature, key.field_blind_index("appointment-link", "abc.confirm"));
+    }
+
+    #[test]
+    fn test_deterministic_encryption() {
+        let key = setup_test_key();
+
+        let first = key.encrypt_deterministic(DeterministicField::FiscalCode, "RSSMRA85T10A562S").unwrap();
+        let second = key.encrypt_deterministic(DeterministicField::FiscalCode, "RSSMRA85T10A562S").unwrap();
+        assert_eq!(first, second);
+        assert!(first.starts_with("siv:fiscal_code:"));
+        assert!(EncryptionKey::is_deterministic(&first));
+        assert_eq!(key.decrypt(&first).unwrap(), "RSSMRA85T10A562S");
+
+        let policy = key.encrypt_deterministic(DeterministicField::PolicyNumber, "RSSMRA85T10A562S").unwrap();
+        assert_ne!(
+            &first["siv:fiscal_code:".len()..],
+            &policy["siv:policy_number:".len()..]
+        );
+
+        // The field is authenticated: relabelled ciphertexts do not decrypt
+        let relabelled = first.replacen("fiscal_code", "policy_number", 1);
+        assert!(key.decrypt(&relabelled).is_err());
+        assert!(!EncryptionKey::is_deterministic(&key.encrypt("RSSMRA85T10A562S").unwrap()));
+    }
+
+    #[test]
+    fn test_prefix_blind_indexes() {
+        let key = setup_test_key();
+
+        let prefixes = key.prefix_blind_indexes("patients.last_name", "rossì", 2, 4);
+        assert_eq!(
+            prefixes,
+            vec![
+                key.field_blind_index("patients.last_name", "ro"),
+                key.field_blind_index("patients.last_name", "ros"),
+                key.field_blind_index("patients.last_name", "ross"),
+            ]
+        );
+        assert_ne!(
+            key.field_blind_index("patients.last_name", "rossi"),
+            key.field_blind_index("patients.email", "rossi")
+        );
+        assert!(key.prefix_blind_indexes("patients.last_name", "r", 2, 4).is_empty());
+    }
+
+    #[test]
+    fn test_trigram_blind_indexes() {
+        let key = setup_test_key();
+
+        let trigrams = key.trigram_blind_indexes("patients.autocomplete", "rossi");
+        assert_eq!(trigrams.len(), 3);
+        assert!(trigrams.iter().all(|t| t.len() == 16));
+        assert!(trigrams.contains(&key.field_blind_index("patients.autocomplete", "oss")[..16].to_string()));
+
+        // Repeated trigrams are indexed once
+        assert_eq!(key.trigram_blind_indexes("patients.autocomplete", "aaaa").len(), 1);
+        assert!(key.trigram_blind_indexes("patients.autocomplete", "ro").is_empty());
+    }
+
     #[test]
     fn test_decrypt_wrong_key_fails() {
         let key1 = setup_test_key();
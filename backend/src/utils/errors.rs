@@ -31,8 +31,14 @@ pub enum AppError {
     Validation(String),
     /// Conflict error (e.g., duplicate resource)
     Conflict(String),
+    /// Record changed since the version the client read (current state)
+    VersionConflict(serde_json::Value),
+    /// Appointment time not available (message, conflicts and alternatives)
+    SchedulingConflict(serde_json::Value),
     /// Rate limit exceeded
     RateLimitExceeded,
+    /// CAPTCHA missing or failed verification
+    CaptchaRequired(String),
     /// Internal server error
     Internal(String),
     /// Bad request
@@ -48,7 +54,16 @@ impl fmt::Display for AppError {
             Self::NotFound(msg) => write!(f, "Not found: {}", msg),
             Self::Validation(msg) => write!(f, "Validation error: {}", msg),
             Self::Conflict(msg) => write!(f, "Conflict: {}", msg),
+            Self::VersionConflict(_) => {
+                write!(f, "Conflict: record was modified by another user")
+            }
+            Self::SchedulingConflict(details) => write!(
+                f,
+                "Conflict: {}",
+                details["message"].as_str().unwrap_or("time not available")
+            ),
             Self::RateLimitExceeded => write!(f, "Rate limit exceeded"),
+            Self::CaptchaRequired(msg) => write!(f, "CAPTCHA required: {}", msg),
             Self::Internal(msg) => write!(f, "Internal server error: {}", msg),
             Self::BadRequest(msg) => write!(f, "Bad request: {}", msg),
         }
@@ -84,47 +99,118 @@ impl From<argon2::password_hash::Error> for AppError {
     }
 }
 
-/// Convert AppError to HTTP response
-impl IntoResponse for AppError {
-    fn into_response(self) -> Response {
-        let (status, error_code, message) = match self {
+impl AppError {
+    /// Machine-readable error code (`error` in v1 bodies, `error.code` in v2)
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Database(_) => "DATABASE_ERROR",
+            Self::Unauthorized(_) => "UNAUTHORIZED",
+            Self::Forbidden(_) => "FORBIDDEN",
+            Self::NotFound(_) => "NOT_FOUND",
+            Self::Validation(_) => "VALIDATION_ERROR",
+            Self::Conflict(_) => "CONFLICT",
+            Self::VersionConflict(_) => "VERSION_CONFLICT",
+            Self::SchedulingConflict(_) => "SCHEDULING_CONFLICT",
+            Self::RateLimitExceeded => "RATE_LIMIT_EXCEEDED",
+            Self::CaptchaRequired(_) => "CAPTCHA_REQUIRED",
+            Self::Internal(_) => "INTERNAL_ERROR",
+            Self::BadRequest(_) => "BAD_REQUEST",
+        }
+    }
+
+    /// Status and JSON body of the error response
+    ///
+    /// Also used for the per-operation results of batch requests.
+    pub fn status_and_body(self) -> (StatusCode, serde_json::Value) {
+        let error_code = self.code();
+        let (status, message) = match self {
             Self::Database(ref err) => {
                 // Don't expose internal database errors to clients
                 tracing::error!("Database error: {}", err);
                 (
                     StatusCode::INTERNAL_SERVER_ERROR,
-                    "DATABASE_ERROR",
                     "An internal database error occurred".to_string(),
                 )
             }
-            Self::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, "UNAUTHORIZED", msg),
-            Self::Forbidden(msg) => (StatusCode::FORBIDDEN, "FORBIDDEN", msg),
-            Self::NotFound(msg) => (StatusCode::NOT_FOUND, "NOT_FOUND", msg),
-            Self::Validation(msg) => (StatusCode::BAD_REQUEST, "VALIDATION_ERROR", msg),
-            Self::Conflict(msg) => (StatusCode::CONFLICT, "CONFLICT", msg),
+            Self::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, msg),
+            Self::Forbidden(msg) => (StatusCode::FORBIDDEN, msg),
+            Self::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
+            Self::Validation(msg) => (StatusCode::BAD_REQUEST, msg),
+            Self::Conflict(msg) => (StatusCode::CONFLICT, msg),
+            Self::VersionConflict(current) => {
+                // The client needs the current record to merge or retry its edit
+                let body = json!({
+                    "error": error_code,
+                    "message": "The record was modified by another user",
+                    "current": current,
+                    "timestamp": chrono::Utc::now().to_rfc3339(),
+                });
+                return (StatusCode::CONFLICT, body);
+            }
+            Self::SchedulingConflict(details) => {
+                // The client offers the alternatives instead of the refused time
+                let body = json!({
+                    "error": error_code,
+                    "message": details["message"],
+                    "conflicts": details["conflicts"],
+                    "alternatives": details["alternatives"],
+                    "overbookable": details["overbookable"].as_bool().unwrap_or(false),
+                    "timestamp": chrono::Utc::now().to_rfc3339(),
+                });
+                return (StatusCode::CONFLICT, body);
+            }
             Self::RateLimitExceeded => (
                 StatusCode::TOO_MANY_REQUESTS,
-                "RATE_LIMIT_EXCEEDED",
                 "Too many requests, please try again later".to_string(),
             ),
+            Self::CaptchaRequired(msg) => (StatusCode::FORBIDDEN, msg),
             Self::Internal(msg) => {
                 tracing::error!("Internal error: {}", msg);
                 (
                     StatusCode::INTERNAL_SERVER_ERROR,
-                    "INTERNAL_ERROR",
                     "An internal server error occurred".to_string(),
                 )
             }
-            Self::BadRequest(msg) => (StatusCode::BAD_REQUEST, "BAD_REQUEST", msg),
+            Self::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
         };
 
-        let body = Json(json!({
+        let body = json!({
             "error": error_code,
             "message": message,
             "timestamp": chrono::Utc::now().to_rfc3339(),
-        }));
+        });
+
+        (status, body)
+    }
 
-        (status, body).into_response()
+    /// Error code for a status without a more specific one
+    ///
+    /// For error responses that do not come from an `AppError` (e.g.
+    /// rejections by Axum or tower layers).
+    pub fn code_for_status(status: StatusCode) -> &'static str {
+        match status {
+            StatusCode::BAD_REQUEST => "BAD_REQUEST",
+            StatusCode::UNAUTHORIZED => "UNAUTHORIZED",
+            StatusCode::FORBIDDEN => "FORBIDDEN",
+            StatusCode::NOT_FOUND => "NOT_FOUND",
+            StatusCode::METHOD_NOT_ALLOWED => "METHOD_NOT_ALLOWED",
+            StatusCode::CONFLICT => "CONFLICT",
+            StatusCode::PAYLOAD_TOO_LARGE => "PAYLOAD_TOO_LARGE",
+            StatusCode::UNSUPPORTED_MEDIA_TYPE => "UNSUPPORTED_MEDIA_TYPE",
+            StatusCode::UNPROCESSABLE_ENTITY => "VALIDATION_ERROR",
+            StatusCode::TOO_MANY_REQUESTS => "RATE_LIMIT_EXCEEDED",
+            StatusCode::SERVICE_UNAVAILABLE => "SERVICE_UNAVAILABLE",
+            status if status.is_server_error() => "INTERNAL_ERROR",
+            _ => "BAD_REQUEST",
+        }
+    }
+}
+
+/// Convert AppError to HTTP response
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let (status, body) = self.status_and_body();
+        (status, Json(body)).into_response()
     }
 }
 
@@ -141,6 +227,31 @@ mod tests {
         assert_eq!(err.to_string(), "Not found: User not found");
     }
 
+    #[test]
+    fn test_app_error_code() {
+        let err = AppError::NotFound("Patient not found".to_string());
+        assert_eq!(err.code(), "NOT_FOUND");
+
+        let (status, body) = AppError::VersionConflict(json!({})).status_and_body();
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["error"], "VERSION_CONFLICT");
+
+        let (status, body) = AppError::SchedulingConflict(json!({
+            "message": "Scheduling conflict detected for provider at this time",
+            "conflicts": [{ "kind": "OVERLAP" }],
+            "alternatives": [],
+        }))
+        .status_and_body();
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["error"], "SCHEDULING_CONFLICT");
+        assert_eq!(body["conflicts"][0]["kind"], "OVERLAP");
+        assert_eq!(body["alternatives"], json!([]));
+        assert_eq!(body["overbookable"], false);
+
+        assert_eq!(AppError::code_for_status(StatusCode::PAYLOAD_TOO_LARGE), "PAYLOAD_TOO_LARGE");
+        assert_eq!(AppError::code_for_status(StatusCode::BAD_GATEWAY), "INTERNAL_ERROR");
+    }
+
     #[test]
     fn test_app_error_from_sqlx() {
         let sqlx_err = sqlx::Error::RowNotFound;
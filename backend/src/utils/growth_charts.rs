@@ -0,0 +1,381 @@
+/*!
+ * Pediatric Growth Charts
+ *
+ * Percentiles of weight, height and BMI for age with the LMS method:
+ * WHO Child Growth Standards from birth to 24 months, CDC 2000 growth
+ * charts from 2 to 20 years (BMI-for-age from 2 years only).
+ *
+ * The LMS parameters are sampled from the published tables at the ages
+ * listed below and interpolated linearly in between.
+ */
+
+use serde::Serialize;
+
+/// Age (months) from which the CDC charts replace the WHO standards
+const CDC_FROM_MONTHS: f64 = 24.0;
+/// Age (months) up to which percentiles are computed
+const PEDIATRIC_UNTIL_MONTHS: f64 = 240.0;
+
+/// Reference growth standard
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum GrowthStandard {
+    /// WHO Child Growth Standards (0-24 months)
+    Who,
+    /// CDC 2000 growth charts (2-20 years)
+    Cdc,
+}
+
+/// Sex the reference tables are split by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GrowthSex {
+    Male,
+    Female,
+}
+
+/// Measurement a percentile is computed for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GrowthMeasure {
+    WeightForAge,
+    /// Recumbent length under 24 months, stature after
+    HeightForAge,
+    BmiForAge,
+}
+
+/// Position of a measurement on the reference distribution
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct GrowthPercentile {
+    pub z_score: f64,
+    /// 0-100, one decimal
+    pub percentile: f64,
+}
+
+/// (age in months, L, M, S)
+type LmsRow = (f64, f64, f64, f64);
+
+const WHO_WEIGHT_BOYS: &[LmsRow] = &[
+    (0.0, 0.3487, 3.3464, 0.14602),
+    (1.0, 0.2297, 4.4709, 0.13395),
+    (2.0, 0.1970, 5.5675, 0.12385),
+    (3.0, 0.1738, 6.3762, 0.11727),
+    (4.0, 0.1553, 7.0023, 0.11316),
+    (6.0, 0.1257, 7.9340, 0.10958),
+    (9.0, 0.0917, 8.9014, 0.10881),
+    (12.0, 0.0644, 9.6479, 0.10925),
+    (15.0, 0.0409, 10.3108, 0.10974),
+    (18.0, 0.0192, 10.9385, 0.11029),
+    (21.0, -0.0006, 11.5486, 0.11088),
+    (24.0, -0.0137, 12.1515, 0.11175),
+];
+
+const WHO_WEIGHT_GIRLS: &[LmsRow] = &[
+    (0.0, 0.3809, 3.2322, 0.14171),
+    (1.0, 0.1714, 4.1873, 0.13724),
+    (2.0, 0.0962, 5.1282, 0.13000),
+    (3.0, 0.0402, 5.8458, 0.12619),
+    (4.0, -0.0050, 6.4237, 0.12402),
+    (6.0, -0.0756, 7.2970, 0.12204),
+    (9.0, -0.1500, 8.2254, 0.12202),
+    (12.0, -0.2024, 8.9481, 0.12268),
+    (15.0, -0.2385, 9.6008, 0.12367),
+    (18.0, -0.2660, 10.2315, 0.12482),
+    (21.0, -0.2884, 10.8534, 0.12605),
+    (24.0, -0.3078, 11.4775, 0.12734),
+];
+
+const WHO_LENGTH_BOYS: &[LmsRow] = &[
+    (0.0, 1.0, 49.8842, 0.03795),
+    (1.0, 1.0, 54.7244, 0.03557),
+    (2.0, 1.0, 58.4249, 0.03424),
+    (3.0, 1.0, 61.4292, 0.03328),
+    (4.0, 1.0, 63.8860, 0.03257),
+    (6.0, 1.0, 67.6236, 0.03165),
+    (9.0, 1.0, 72.0351, 0.03113),
+    (12.0, 1.0, 75.7488, 0.03137),
+    (15.0, 1.0, 79.1458, 0.03191),
+    (18.0, 1.0, 82.2587, 0.03253),
+    (21.0, 1.0, 85.1191, 0.03312),
+    (24.0, 1.0, 87.8161, 0.03372),
+];
+
+const WHO_LENGTH_GIRLS: &[LmsRow] = &[
+    (0.0, 1.0, 49.1477, 0.03790),
+    (1.0, 1.0, 53.6872, 0.03640),
+    (2.0, 1.0, 57.0673, 0.03568),
+    (3.0, 1.0, 59.8029, 0.03520),
+    (4.0, 1.0, 62.0899, 0.03486),
+    (6.0, 1.0, 65.7311, 0.03441),
+    (9.0, 1.0, 70.1435, 0.03421),
+    (12.0, 1.0, 74.0150, 0.03459),
+    (15.0, 1.0, 77.5099, 0.03517),
+    (18.0, 1.0, 80.7079, 0.03578),
+    (21.0, 1.0, 83.6654, 0.03640),
+    (24.0, 1.0, 86.4153, 0.03702),
+];
+
+const CDC_WEIGHT_BOYS: &[LmsRow] = &[
+    (24.0, -0.2162, 12.74, 0.1085),
+    (36.0, -0.6680, 14.34, 0.1101),
+    (48.0, -1.0000, 16.34, 0.1158),
+    (60.0, -1.1890, 18.40, 0.1232),
+    (72.0, -1.2320, 20.69, 0.1325),
+    (96.0, -1.0900, 25.65, 0.1530),
+    (120.0, -0.8400, 31.44, 0.1720),
+    (144.0, -0.5700, 39.90, 0.1800),
+    (168.0, -0.3900, 50.80, 0.1730),
+    (192.0, -0.3500, 61.30, 0.1560),
+    (216.0, -0.4600, 68.90, 0.1450),
+    (240.0, -0.6000, 70.60, 0.1420),
+];
+
+const CDC_WEIGHT_GIRLS: &[LmsRow] = &[
+    (24.0, -0.7387, 12.14, 0.1099),
+    (36.0, -0.9000, 14.00, 0.1180),
+    (48.0, -1.0000, 15.94, 0.1260),
+    (60.0, -1.0300, 17.91, 0.1340),
+    (72.0, -1.0100, 20.20, 0.1420),
+    (96.0, -0.8700, 25.64, 0.1600),
+    (120.0, -0.6500, 32.55, 0.1750),
+    (144.0, -0.4600, 41.52, 0.1760),
+    (168.0, -0.3600, 49.40, 0.1680),
+    (192.0, -0.4300, 53.50, 0.1620),
+    (216.0, -0.6200, 56.20, 0.1620),
+    (240.0, -0.8400, 58.20, 0.1660),
+];
+
+const CDC_STATURE_BOYS: &[LmsRow] = &[
+    (24.0, 0.9417, 86.45, 0.0403),
+    (36.0, 1.0, 95.27, 0.0410),
+    (48.0, 1.0, 102.47, 0.0420),
+    (60.0, 1.0, 109.18, 0.0424),
+    (72.0, 1.0, 115.53, 0.0428),
+    (96.0, 1.0, 127.00, 0.0433),
+    (120.0, 1.0, 137.80, 0.0437),
+    (144.0, 1.0, 149.10, 0.0470),
+    (168.0, 1.0, 163.20, 0.0480),
+    (192.0, 1.0, 173.40, 0.0420),
+    (216.0, 1.0, 176.10, 0.0400),
+    (240.0, 1.0, 176.80, 0.0400),
+];
+
+const CDC_STATURE_GIRLS: &[LmsRow] = &[
+    (24.0, 1.0, 85.40, 0.0404),
+    (36.0, 1.0, 94.12, 0.0418),
+    (48.0, 1.0, 101.59, 0.0428),
+    (60.0, 1.0, 108.40, 0.0435),
+    (72.0, 1.0, 115.00, 0.0440),
+    (96.0, 1.0, 127.50, 0.0445),
+    (120.0, 1.0, 138.60, 0.0460),
+    (144.0, 1.0, 151.50, 0.0460),
+    (168.0, 1.0, 159.80, 0.0410),
+    (192.0, 1.0, 162.50, 0.0390),
+    (216.0, 1.0, 163.20, 0.0390),
+    (240.0, 1.0, 163.30, 0.0390),
+];
+
+const CDC_BMI_BOYS: &[LmsRow] = &[
+    (24.0, -2.0112, 16.58, 0.0806),
+    (36.0, -1.9000, 16.02, 0.0760),
+    (48.0, -1.7000, 15.61, 0.0770),
+    (60.0, -1.5500, 15.42, 0.0800),
+    (72.0, -1.5000, 15.38, 0.0860),
+    (96.0, -1.5800, 15.78, 0.1000),
+    (120.0, -1.7000, 16.63, 0.1180),
+    (144.0, -1.7800, 17.90, 0.1300),
+    (168.0, -1.7000, 19.30, 0.1330),
+    (192.0, -1.5300, 20.52, 0.1300),
+    (216.0, -1.3600, 21.68, 0.1270),
+    (240.0, -1.2500, 22.60, 0.1250),
+];
+
+const CDC_BMI_GIRLS: &[LmsRow] = &[
+    (24.0, -0.9878, 16.42, 0.0852),
+    (36.0, -1.3000, 15.73, 0.0840),
+    (48.0, -1.6000, 15.30, 0.0880),
+    (60.0, -1.8500, 15.20, 0.0940),
+    (72.0, -2.0000, 15.31, 0.1020),
+    (96.0, -2.1000, 15.90, 0.1180),
+    (120.0, -2.0500, 16.98, 0.1330),
+    (144.0, -1.9000, 18.40, 0.1420),
+    (168.0, -1.7000, 19.80, 0.1420),
+    (192.0, -1.5600, 20.74, 0.1400),
+    (216.0, -1.4600, 21.33, 0.1400),
+    (240.0, -1.4000, 21.71, 0.1420),
+];
+
+/// Whether growth percentiles apply at this age
+pub fn is_pediatric(age_months: f64) -> bool {
+    (0.0..PEDIATRIC_UNTIL_MONTHS).contains(&age_months)
+}
+
+/// Standard used at this age, None for adults
+pub fn standard_for_age(age_months: f64) -> Option<GrowthStandard> {
+    if !is_pediatric(age_months) {
+        None
+    } else if age_months < CDC_FROM_MONTHS {
+        Some(GrowthStandard::Who)
+    } else {
+        Some(GrowthStandard::Cdc)
+    }
+}
+
+/// Percentile of a measurement for age and sex
+///
+/// None outside 0-20 years, for BMI under 2 years, or for a non-positive
+/// value.
+pub fn growth_percentile(
+    sex: GrowthSex,
+    measure: GrowthMeasure,
+    age_months: f64,
+    value: f64,
+) -> Option<GrowthPercentile> {
+    if value <= 0.0 {
+        return None;
+    }
+
+    let table = reference_table(standard_for_age(age_months)?, sex, measure)?;
+    let (l, m, s) = interpolate_lms(table, age_months);
+    let z_score = lms_z_score(value, l, m, s);
+
+    Some(GrowthPercentile {
+        z_score: (z_score * 100.0).round() / 100.0,
+        percentile: (normal_cdf(z_score) * 1000.0).round() / 10.0,
+    })
+}
+
+fn reference_table(
+    standard: GrowthStandard,
+    sex: GrowthSex,
+    measure: GrowthMeasure,
+) -> Option<&'static [LmsRow]> {
+    use GrowthMeasure::*;
+    use GrowthSex::*;
+
+    match (standard, measure, sex) {
+        (GrowthStandard::Who, WeightForAge, Male) => Some(WHO_WEIGHT_BOYS),
+        (GrowthStandard::Who, WeightForAge, Female) => Some(WHO_WEIGHT_GIRLS),
+        (GrowthStandard::Who, HeightForAge, Male) => Some(WHO_LENGTH_BOYS),
+        (GrowthStandard::Who, HeightForAge, Female) => Some(WHO_LENGTH_GIRLS),
+        (GrowthStandard::Who, BmiForAge, _) => None,
+        (GrowthStandard::Cdc, WeightForAge, Male) => Some(CDC_WEIGHT_BOYS),
+        (GrowthStandard::Cdc, WeightForAge, Female) => Some(CDC_WEIGHT_GIRLS),
+        (GrowthStandard::Cdc, HeightForAge, Male) => Some(CDC_STATURE_BOYS),
+        (GrowthStandard::Cdc, HeightForAge, Female) => Some(CDC_STATURE_GIRLS),
+        (GrowthStandard::Cdc, BmiForAge, Male) => Some(CDC_BMI_BOYS),
+        (GrowthStandard::Cdc, BmiForAge, Female) => Some(CDC_BMI_GIRLS),
+    }
+}
+
+/// L, M and S at an age, interpolated between the sampled ages
+fn interpolate_lms(table: &[LmsRow], age_months: f64) -> (f64, f64, f64) {
+    let first = table[0];
+    if age_months <= first.0 {
+        return (first.1, first.2, first.3);
+    }
+
+    for pair in table.windows(2) {
+        let (lower, upper) = (pair[0], pair[1]);
+        if age_months <= upper.0 {
+            let t = (age_months - lower.0) / (upper.0 - lower.0);
+            return (
+                lower.1 + t * (upper.1 - lower.1),
+                lower.2 + t * (upper.2 - lower.2),
+                lower.3 + t * (upper.3 - lower.3),
+            );
+        }
+    }
+
+    let last = table[table.len() - 1];
+    (last.1, last.2, last.3)
+}
+
+/// Z-score of a value on an LMS distribution
+fn lms_z_score(value: f64, l: f64, m: f64, s: f64) -> f64 {
+    if l.abs() < 1e-6 {
+        (value / m).ln() / s
+    } else {
+        ((value / m).powf(l) - 1.0) / (l * s)
+    }
+}
+
+/// Standard normal cumulative distribution
+fn normal_cdf(z: f64) -> f64 {
+    0.5 * (1.0 + erf(z / std::f64::consts::SQRT_2))
+}
+
+/// Error function (Abramowitz and Stegun 7.1.26, error below 1.5e-7)
+fn erf(x: f64) -> f64 {
+    let sign = if x < 0.0 { -1.0 } else { 1.0 };
+    let x = x.abs();
+    let t = 1.0 / (1.0 + 0.3275911 * x);
+    let poly = t
+        * (0.254829592
+            + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    sign * (1.0 - poly * (-x * x).exp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_median_is_50th_percentile() {
+        let p =
+            growth_percentile(GrowthSex::Male, GrowthMeasure::WeightForAge, 0.0, 3.3464).unwrap();
+        assert_eq!(p.z_score, 0.0);
+        assert_eq!(p.percentile, 50.0);
+
+        let p = growth_percentile(GrowthSex::Female, GrowthMeasure::HeightForAge, 120.0, 138.6)
+            .unwrap();
+        assert_eq!(p.percentile, 50.0);
+    }
+
+    #[test]
+    fn test_percentile_ordering() {
+        let low =
+            growth_percentile(GrowthSex::Male, GrowthMeasure::WeightForAge, 6.0, 6.5).unwrap();
+        let high =
+            growth_percentile(GrowthSex::Male, GrowthMeasure::WeightForAge, 6.0, 9.5).unwrap();
+        assert!(low.z_score < -1.5);
+        assert!(high.z_score > 1.5);
+        assert!(low.percentile < 10.0 && high.percentile > 90.0);
+    }
+
+    #[test]
+    fn test_interpolation_between_sampled_ages() {
+        // Halfway between the 6 and 9 month medians
+        let median = (7.9340 + 8.9014) / 2.0;
+        let p =
+            growth_percentile(GrowthSex::Male, GrowthMeasure::WeightForAge, 7.5, median).unwrap();
+        assert!(p.z_score.abs() < 0.01);
+    }
+
+    #[test]
+    fn test_standard_by_age() {
+        assert_eq!(standard_for_age(0.0), Some(GrowthStandard::Who));
+        assert_eq!(standard_for_age(23.9), Some(GrowthStandard::Who));
+        assert_eq!(standard_for_age(24.0), Some(GrowthStandard::Cdc));
+        assert_eq!(standard_for_age(240.0), None);
+        assert_eq!(standard_for_age(-1.0), None);
+    }
+
+    #[test]
+    fn test_no_percentile_outside_references() {
+        assert!(growth_percentile(GrowthSex::Male, GrowthMeasure::BmiForAge, 12.0, 17.0).is_none());
+        assert!(growth_percentile(GrowthSex::Male, GrowthMeasure::BmiForAge, 36.0, 16.0).is_some());
+        assert!(
+            growth_percentile(GrowthSex::Female, GrowthMeasure::WeightForAge, 300.0, 60.0)
+                .is_none()
+        );
+        assert!(
+            growth_percentile(GrowthSex::Female, GrowthMeasure::WeightForAge, 12.0, 0.0).is_none()
+        );
+    }
+
+    #[test]
+    fn test_normal_cdf() {
+        assert!((normal_cdf(0.0) - 0.5).abs() < 1e-7);
+        assert!((normal_cdf(1.96) - 0.975).abs() < 1e-3);
+        assert!((normal_cdf(-1.0) - 0.1587).abs() < 1e-3);
+    }
+}
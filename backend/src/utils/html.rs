@@ -0,0 +1,30 @@
+/*!
+ * HTML Escaping
+ *
+ * Escaping of values interpolated into HTML: email bodies (portal sign-in
+ * links, invitations, online booking) and search highlights.
+ */
+
+/// Escape `&`, `<`, `>` and `"`, so the value is safe in element content and
+/// double-quoted attributes
+pub fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(
+            escape_html(r#"<a href="x">Rossi & Figli</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;Rossi &amp; Figli&lt;/a&gt;"
+        );
+        assert_eq!(escape_html("Mario Rossi"), "Mario Rossi");
+    }
+}
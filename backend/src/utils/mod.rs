@@ -4,15 +4,22 @@
  * Contains utility functions for error handling, validation, and encryption.
  */
 
+pub mod barcode;
+pub mod concurrency;
 pub mod encryption;
 pub mod errors;
+pub mod growth_charts;
+pub mod html;
+pub mod jwt_keys;
 pub mod password;
+pub mod text_search;
 pub mod validators;
 
 #[cfg(feature = "rbac")]
 pub mod permissions;
 
-pub use encryption::EncryptionKey;
+pub use encryption::{DeterministicField, EncryptionKey};
 pub use errors::{AppError, Result};
+pub use jwt_keys::{JwtAlgorithm, JwtKeyRing};
 pub use password::PasswordHasherUtil;
 pub use validators::{validate_uuid, FiscalCodeValidator, PhoneValidator};
@@ -0,0 +1,188 @@
+/*!
+ * Text Search Helpers
+ *
+ * Word splitting and highlighting for searching encrypted free text. The
+ * database only ever sees keyed hashes of the normalized words (see
+ * `EncryptionKey::blind_index`), so normalization happens here: words are
+ * runs of letters and digits, lowercased, with Italian accents folded
+ * ("Perché" matches "perche"). There is no stemming or prefix matching.
+ */
+
+use std::ops::Range;
+
+use super::html::escape_html;
+
+/// Shortest word indexed or searched for
+pub const MIN_TERM_CHARS: usize = 2;
+
+/// Longest word indexed or searched for; longer runs are not words
+pub const MAX_TERM_CHARS: usize = 64;
+
+/// Characters of context kept on each side of the first match in a snippet
+const SNIPPET_CONTEXT_CHARS: usize = 60;
+
+/// Words of `text` with their byte range, normalized for matching
+pub fn words(text: &str) -> Vec<(Range<usize>, String)> {
+    let mut words = Vec::new();
+    let mut start = None;
+
+    for (i, c) in text
+        .char_indices()
+        .chain(std::iter::once((text.len(), ' ')))
+    {
+        match (c.is_alphanumeric(), start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                let word = normalize(&text[s..i]);
+                let chars = word.chars().count();
+                if (MIN_TERM_CHARS..=MAX_TERM_CHARS).contains(&chars) {
+                    words.push((s..i, word));
+                }
+                start = None;
+            }
+            _ => {}
+        }
+    }
+
+    words
+}
+
+/// Distinct words of a search query, in order
+pub fn search_terms(query: &str) -> Vec<String> {
+    let mut terms: Vec<String> = Vec::new();
+    for (_, word) in words(query) {
+        if !terms.contains(&word) {
+            terms.push(word);
+        }
+    }
+    terms
+}
+
+/// Snippet of `text` around its first word among `terms`, HTML-escaped,
+/// with every matching word wrapped in `<mark>`; none when nothing matches
+pub fn highlight(text: &str, terms: &[String]) -> Option<String> {
+    let matches: Vec<Range<usize>> = words(text)
+        .into_iter()
+        .filter(|(_, word)| terms.contains(word))
+        .map(|(range, _)| range)
+        .collect();
+    let first = matches.first()?;
+
+    let start = back_chars(text, first.start, SNIPPET_CONTEXT_CHARS);
+    let end = forward_chars(text, first.end, SNIPPET_CONTEXT_CHARS);
+
+    let mut snippet = String::new();
+    if start > 0 {
+        snippet.push('…');
+    }
+    let mut cursor = start;
+    for range in matches.iter().filter(|r| r.start >= start && r.end <= end) {
+        snippet.push_str(&escape_html(&text[cursor..range.start]));
+        snippet.push_str("<mark>");
+        snippet.push_str(&escape_html(&text[range.clone()]));
+        snippet.push_str("</mark>");
+        cursor = range.end;
+    }
+    snippet.push_str(&escape_html(&text[cursor..end]));
+    if end < text.len() {
+        snippet.push('…');
+    }
+
+    Some(snippet.trim().to_string())
+}
+
+/// Lowercase with accented Latin letters folded to their base letter
+pub fn normalize(word: &str) -> String {
+    word.chars()
+        .flat_map(char::to_lowercase)
+        .map(|c| match c {
+            'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' => 'a',
+            'è' | 'é' | 'ê' | 'ë' => 'e',
+            'ì' | 'í' | 'î' | 'ï' => 'i',
+            'ò' | 'ó' | 'ô' | 'õ' | 'ö' => 'o',
+            'ù' | 'ú' | 'û' | 'ü' => 'u',
+            'ç' => 'c',
+            'ñ' => 'n',
+            other => other,
+        })
+        .collect()
+}
+
+/// Byte offset `chars` characters before `from`, moved back to the start
+/// of the word it falls in
+fn back_chars(text: &str, from: usize, chars: usize) -> usize {
+    let mut offset = text[..from]
+        .char_indices()
+        .rev()
+        .nth(chars.saturating_sub(1))
+        .map(|(i, _)| i)
+        .unwrap_or(0);
+    while offset > 0 {
+        let previous = text[..offset].chars().next_back();
+        if !previous.is_some_and(char::is_alphanumeric) {
+            break;
+        }
+        offset -= previous.map(char::len_utf8).unwrap_or(1);
+    }
+    offset
+}
+
+/// Byte offset `chars` characters after `from`, moved forward to the end
+/// of the word it falls in
+fn forward_chars(text: &str, from: usize, chars: usize) -> usize {
+    let mut offset = text[from..]
+        .char_indices()
+        .nth(chars)
+        .map(|(i, _)| from + i)
+        .unwrap_or(text.len());
+    while let Some(next) = text[offset..].chars().next() {
+        if !next.is_alphanumeric() {
+            break;
+        }
+        offset += next.len_utf8();
+    }
+    offset
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_words_normalized() {
+        let words: Vec<String> = words("Sindrome del Tunnel carpale, perché? Dx: 3° dito")
+            .into_iter()
+            .map(|(_, word)| word)
+            .collect();
+        assert_eq!(
+            words,
+            vec!["sindrome", "del", "tunnel", "carpale", "perche", "dx", "dito"]
+        );
+
+        assert_eq!(
+            search_terms("carpal Tunnel tunnel"),
+            vec!["carpal", "tunnel"]
+        );
+        assert!(search_terms("  ? ").is_empty());
+    }
+
+    #[test]
+    fn test_highlight() {
+        let text = "Numbness of the first three fingers and paresthesia in the right hand, worse at night. Suspected carpal \
+                    tunnel syndrome; Tinel <positive>. Refer for EMG of the median nerve \
+                    to confirm and grade the carpal tunnel.";
+        let terms = search_terms("carpal tunnel");
+
+        let snippet = highlight(text, &terms).unwrap();
+        assert!(snippet.starts_with('…'));
+        assert!(snippet.ends_with('…'));
+        assert!(snippet.contains("<mark>carpal</mark> <mark>tunnel</mark> syndrome"));
+        assert!(snippet.contains("Tinel &lt;positive&gt;"));
+
+        assert_eq!(
+            highlight("Follow-up for Carpal tunnel", &terms).as_deref(),
+            Some("Follow-up for <mark>Carpal</mark> <mark>tunnel</mark>")
+        );
+        assert_eq!(highlight(text, &search_terms("migraine")), None);
+    }
+}
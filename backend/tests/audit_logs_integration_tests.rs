@@ -9,6 +9,7 @@
  * - Export audit logs (GET /api/v1/audit-logs/export)
  * - Get filter options (GET /api/v1/audit-logs/filter-options)
  * - RBAC permission enforcement (ADMIN only)
+ * - Audit middleware on the nested /api/v1 and /api/v2 routers
  */
 
 use axum::{
@@ -73,6 +74,33 @@ async fn login_and_get_token(app: &axum::Router, username: &str, password: &str)
     json["tokens"]["access_token"].as_str().unwrap().to_string()
 }
 
+/// Helper function to read a user's audit log actions, oldest first
+///
+/// Audit rows are written in the background after the response is sent, so
+/// this polls until at least `expected` rows exist (or gives up).
+async fn audit_actions_for(pool: &sqlx::PgPool, user_id: Uuid, expected: usize) -> Vec<String> {
+    let mut actions = Vec::new();
+    for _ in 0..50 {
+        let mut tx = pool.begin().await.expect("Failed to begin transaction");
+        sqlx::query("SELECT set_config('app.current_user_role', 'ADMIN', true)")
+            .execute(&mut *tx)
+            .await
+            .expect("Failed to set RLS role");
+        actions = sqlx::query_scalar("SELECT action FROM audit_logs WHERE user_id = $1 ORDER BY id")
+            .bind(user_id)
+            .fetch_all(&mut *tx)
+            .await
+            .expect("Failed to read audit logs");
+        tx.commit().await.expect("Failed to commit transaction");
+
+        if actions.len() >= expected {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    actions
+}
+
 /// Helper function to create some audit log entries for testing
 async fn create_test_audit_logs(pool: &sqlx::PgPool, user_id: Uuid, count: i32) {
     for i in 0..count {
@@ -735,3 +763,154 @@ async fn test_list_audit_logs_filter_by_user() {
         }
     }
 }
+
+// ============================================================================
+// Test: Audit Middleware
+// ============================================================================
+
+#[tokio::test]
+#[cfg(feature = "rbac")]
+async fn test_audit_middleware_logs_nested_api_requests() {
+    let (app, pool) = setup_test().await;
+    let suffix = unique_suffix();
+
+    let admin = TestUser::create_admin_user(&pool, &format!("admin_mw_{}", suffix), "Zk9$mX2vL!").await;
+    let token = login_and_get_token(&app, &admin.username, "Zk9$mX2vL!").await;
+
+    // The same write through both API versions
+    for (version, date) in [("v1", "2027-03-15"), ("v2", "2027-03-16")] {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri(format!("/api/{}/holidays", version))
+                    .header("authorization", format!("Bearer {}", token))
+                    .header("content-type", "application/json")
+                    .body(Body::from(
+                        json!({
+                            "holiday_date": date,
+                            "name": "Audit Middleware Day",
+                            "holiday_type": "PRACTICE_CLOSED",
+                            "is_recurring": false
+                        })
+                        .to_string(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+
+    // Each request leaves the handler's CREATE row and the middleware's request row
+    let actions = audit_actions_for(&pool, admin.id, 4).await;
+    assert!(actions.contains(&"POST /api/v1/holidays".to_string()), "{:?}", actions);
+    assert!(actions.contains(&"POST /api/v2/holidays".to_string()), "{:?}", actions);
+    assert_eq!(actions.iter().filter(|a| *a == "CREATE").count(), 2, "{:?}", actions);
+}
+
+#[tokio::test]
+#[cfg(feature = "rbac")]
+async fn test_impersonated_write_records_both_users() {
+    let (app, pool) = setup_test().await;
+    let suffix = unique_suffix();
+
+    let admin = TestUser::create_admin_user(&pool, &format!("admin_imp_{}", suffix), "Zk9$mX2vL!").await;
+    let doctor =
+        TestUser::create_active_user(&pool, &format!("doctor_imp_{}", suffix), "Zk9$mX2vL!", false).await;
+    let admin_token = login_and_get_token(&app, &admin.username, "Zk9$mX2vL!").await;
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(format!("/api/v1/users/{}/impersonate", doctor.id))
+                .header("authorization", format!("Bearer {}", admin_token))
+                .header("content-type", "application/json")
+                .body(Body::from(json!({ "reason": "Ticket #42" }).to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let body = body_to_bytes(response.into_body()).await;
+    let json: Value = serde_json::from_slice(&body).unwrap();
+    let impersonation_token = json["access_token"].as_str().unwrap().to_string();
+
+    // The end handler writes its own audit row while acting as the doctor
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/v1/auth/impersonation/end")
+                .header("authorization", format!("Bearer {}", impersonation_token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    // Handler row and middleware request row
+    let actions = audit_actions_for(&pool, doctor.id, 2).await;
+    assert!(actions.contains(&"UPDATE".to_string()), "{:?}", actions);
+
+    let mut tx = pool.begin().await.unwrap();
+    sqlx::query("SELECT set_config('app.current_user_role', 'ADMIN', true)")
+        .execute(&mut *tx)
+        .await
+        .unwrap();
+    let rows: Vec<(String, Option<Uuid>)> =
+        sqlx::query_as("SELECT action, impersonator_id FROM audit_logs WHERE user_id = $1")
+            .bind(doctor.id)
+            .fetch_all(&mut *tx)
+            .await
+            .unwrap();
+    tx.commit().await.unwrap();
+
+    for (action, impersonator_id) in rows {
+        assert_eq!(impersonator_id, Some(admin.id), "{} row lost the impersonator", action);
+    }
+}
+
+#[tokio::test]
+async fn test_unauthorized_api_request_is_sent_to_siem() {
+    use docpat_backend::{config::Config, services::siem_exporter};
+
+    let (app, _pool) = setup_test().await;
+
+    // Local syslog collector
+    let collector = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    std::env::set_var("SIEM_ENDPOINT", format!("udp://{}", collector.local_addr().unwrap()));
+    std::env::set_var("SIEM_FORMAT", "json");
+    let siem = Config::load_siem_config().unwrap().unwrap();
+    siem_exporter::init_siem_exporter(siem);
+    std::env::remove_var("SIEM_ENDPOINT");
+    std::env::remove_var("SIEM_FORMAT");
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/api/v1/patients")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let mut buf = vec![0u8; 8192];
+    let len = tokio::time::timeout(std::time::Duration::from_secs(5), collector.recv(&mut buf))
+        .await
+        .expect("No security event reached the SIEM")
+        .unwrap();
+    let message = String::from_utf8_lossy(&buf[..len]);
+    assert!(message.contains("AUTH_FAILURE"), "{}", message);
+    assert!(message.contains("GET /api/v1/patients"), "{}", message);
+}
@@ -7,6 +7,7 @@
  * - Patient notification preferences
  * - Email status and test email
  * - RBAC permission enforcement
+ * - Worker claim locking, lease expiry and the stuck-state janitor
  */
 
 use axum::{
@@ -1274,3 +1275,188 @@ async fn test_complete_notification_workflow() {
 
     teardown_test_db(&pool).await;
 }
+
+// ============================================================================
+// WORKER CLAIM TESTS
+// ============================================================================
+
+/// Insert a queued notification directly, bypassing the API
+///
+/// `lease_minutes` sets `lease_expires_at` relative to now (negative = expired)
+/// and `age_minutes` backdates `updated_at`.
+async fn insert_queued_notification(
+    pool: &sqlx::PgPool,
+    status: &str,
+    claimed_by: Option<&str>,
+    lease_minutes: Option<i64>,
+    age_minutes: i64,
+) -> Uuid {
+    sqlx::query_scalar(
+        r#"
+        INSERT INTO notification_queue (
+            notification_type, delivery_method, recipient_email, message_body,
+            scheduled_for, status, claimed_by, claimed_at, lease_expires_at, updated_at
+        )
+        VALUES (
+            'CUSTOM', 'EMAIL', 'worker-test@example.com', 'Worker claim test',
+            NOW() - INTERVAL '1 minute', $1, $2,
+            CASE WHEN $2::text IS NULL THEN NULL ELSE NOW() - make_interval(mins => $4::int) END,
+            NOW() + make_interval(mins => $3::int),
+            NOW() - make_interval(mins => $4::int)
+        )
+        RETURNING id
+        "#,
+    )
+    .bind(status)
+    .bind(claimed_by)
+    .bind(lease_minutes)
+    .bind(age_minutes)
+    .fetch_one(pool)
+    .await
+    .expect("Failed to insert queued notification")
+}
+
+/// Status and lease owner of a queued notification
+async fn queued_notification_state(pool: &sqlx::PgPool, id: Uuid) -> (String, Option<String>) {
+    sqlx::query_as("SELECT status, claimed_by FROM notification_queue WHERE id = $1")
+        .bind(id)
+        .fetch_one(pool)
+        .await
+        .expect("Failed to read queued notification")
+}
+
+/// Test: Concurrent workers never claim the same notification
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_concurrent_claimers_never_claim_same_notification() {
+    use docpat_backend::services::{EmailService, NotificationService};
+    use std::collections::HashSet;
+
+    let (_app, pool) = setup_test().await;
+    let admin = TestUser::create_admin_user(
+        &pool,
+        &format!("claim_admin{}", unique_suffix()),
+        "ValidPass123!",
+    )
+    .await;
+
+    let mut queued = HashSet::new();
+    for _ in 0..40 {
+        queued.insert(insert_queued_notification(&pool, "PENDING", None, None, 0).await);
+    }
+
+    let worker = || {
+        NotificationService::new(
+            pool.clone(),
+            EmailService::new(None).expect("Failed to create disabled email service"),
+        )
+    };
+    let (first, second) = (worker(), worker());
+
+    // Both claimers race over the same rows in batches smaller than the queue
+    let admin_id = admin.id;
+    let claim_all = move |service: NotificationService| async move {
+        let mut claimed = Vec::new();
+        loop {
+            let batch = service
+                .get_pending_notifications(7, admin_id)
+                .await
+                .expect("Failed to claim notifications");
+            if batch.is_empty() {
+                break claimed;
+            }
+            claimed.extend(batch.into_iter().map(|n| n.id));
+        }
+    };
+    let first = tokio::spawn(claim_all(first));
+    let second = tokio::spawn(claim_all(second));
+    let claimed_first = first.await.unwrap();
+    let claimed_second = second.await.unwrap();
+
+    let mut seen = HashSet::new();
+    for id in claimed_first.iter().chain(claimed_second.iter()) {
+        assert!(seen.insert(*id), "Notification {} was claimed twice", id);
+    }
+    assert_eq!(seen, queued);
+
+    for id in &queued {
+        let (status, claimed_by) = queued_notification_state(&pool, *id).await;
+        assert_eq!(status, "PROCESSING");
+        assert!(claimed_by.is_some());
+    }
+
+    teardown_test_db(&pool).await;
+}
+
+/// Test: A notification whose lease expired is reclaimed; a live lease is not
+#[tokio::test]
+async fn test_expired_lease_is_reclaimed() {
+    use docpat_backend::services::{EmailService, NotificationService};
+
+    let (_app, pool) = setup_test().await;
+    let admin = TestUser::create_admin_user(
+        &pool,
+        &format!("lease_admin{}", unique_suffix()),
+        "ValidPass123!",
+    )
+    .await;
+
+    let expired =
+        insert_queued_notification(&pool, "PROCESSING", Some("crashed-worker"), Some(-1), 10).await;
+    let live =
+        insert_queued_notification(&pool, "PROCESSING", Some("live-worker"), Some(5), 10).await;
+
+    let service = NotificationService::new(
+        pool.clone(),
+        EmailService::new(None).expect("Failed to create disabled email service"),
+    );
+    let claimed: Vec<Uuid> = service
+        .get_pending_notifications(10, admin.id)
+        .await
+        .expect("Failed to claim notifications")
+        .into_iter()
+        .map(|n| n.id)
+        .collect();
+
+    assert_eq!(claimed, vec![expired]);
+
+    let (_, expired_owner) = queued_notification_state(&pool, expired).await;
+    assert_ne!(expired_owner.as_deref(), Some("crashed-worker"));
+    let (_, live_owner) = queued_notification_state(&pool, live).await;
+    assert_eq!(live_owner.as_deref(), Some("live-worker"));
+
+    teardown_test_db(&pool).await;
+}
+
+/// Test: The janitor fails stuck notifications but leaves live leases alone
+#[tokio::test]
+async fn test_janitor_respects_live_leases() {
+    use docpat_backend::services::janitor_service::{JanitorConfig, StuckStateJanitor};
+    use docpat_backend::services::SettingsService;
+    use std::sync::Arc;
+
+    let (_app, pool) = setup_test().await;
+
+    // Both have been PROCESSING for longer than the janitor tolerates
+    let stuck =
+        insert_queued_notification(&pool, "PROCESSING", Some("crashed-worker"), Some(-5), 60).await;
+    let leased =
+        insert_queued_notification(&pool, "PROCESSING", Some("live-worker"), Some(5), 60).await;
+
+    let janitor =
+        StuckStateJanitor::new(pool.clone(), Arc::new(SettingsService::new(pool.clone())));
+    let report = janitor
+        .run(&JanitorConfig::default())
+        .await
+        .expect("Janitor run failed");
+    assert_eq!(report.notifications_failed, 1);
+
+    let (stuck_status, stuck_owner) = queued_notification_state(&pool, stuck).await;
+    assert_eq!(stuck_status, "FAILED");
+    assert_eq!(stuck_owner, None);
+
+    let (leased_status, leased_owner) = queued_notification_state(&pool, leased).await;
+    assert_eq!(leased_status, "PROCESSING");
+    assert_eq!(leased_owner.as_deref(), Some("live-worker"));
+
+    teardown_test_db(&pool).await;
+}
@@ -7,7 +7,7 @@
  * - Update patient (PUT /api/v1/patients/:id)
  * - Delete patient (DELETE /api/v1/patients/:id)
  * - List patients (GET /api/v1/patients)
- * - Search patients (GET /api/v1/patients/search)
+ * - Search patients (GET /api/v1/patients/search), including blind index lookups
  * - Get statistics (GET /api/v1/patients/statistics)
  * - Duplicate detection (fiscal code, name+DOB)
  * - Data encryption/decryption round-trip
@@ -831,7 +831,7 @@ async fn test_update_nonexistent_patient() {
 // DELETE PATIENT TESTS
 // ============================================================================
 
-/// Test: Admin can delete (deactivate) patient
+/// Test: Admin can delete a patient into the recycle bin and restore it
 #[tokio::test]
 #[cfg(feature = "rbac")]
 async fn test_delete_patient_as_admin_success() {
@@ -861,7 +861,61 @@ async fn test_delete_patient_as_admin_success() {
 
     assert_eq!(response.status(), StatusCode::NO_CONTENT);
 
-    // Verify patient status is INACTIVE
+    // Verify the patient is hidden while in the recycle bin
+    let get_response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri(format!("/api/v1/patients/{}", patient_id))
+                .header("authorization", format!("Bearer {}", admin_token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(get_response.status(), StatusCode::NOT_FOUND);
+
+    // Verify the patient is listed in the recycle bin
+    let trash_response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/api/v1/trash?entity_type=PATIENT")
+                .header("authorization", format!("Bearer {}", admin_token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(trash_response.status(), StatusCode::OK);
+    let body = body_to_bytes(trash_response.into_body()).await;
+    let json: Value = serde_json::from_slice(&body).unwrap();
+    assert!(json["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|item| item["id"] == patient_id));
+
+    // Restore it
+    let restore_response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(format!("/api/v1/trash/patients/{}/restore", patient_id))
+                .header("authorization", format!("Bearer {}", admin_token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(restore_response.status(), StatusCode::NO_CONTENT);
+
     let get_response = app
         .clone()
         .oneshot(
@@ -877,7 +931,7 @@ async fn test_delete_patient_as_admin_success() {
 
     let body = body_to_bytes(get_response.into_body()).await;
     let json: Value = serde_json::from_slice(&body).unwrap();
-    assert_eq!(json["status"], "INACTIVE");
+    assert_eq!(json["status"], "ACTIVE");
 
     teardown_test_db(&pool).await;
 }
@@ -1171,6 +1225,43 @@ async fn test_search_patients_by_gender() {
     teardown_test_db(&pool).await;
 }
 
+/// Test: Search patients by last name prefix (blind index)
+#[tokio::test]
+#[cfg(feature = "rbac")]
+async fn test_search_patients_by_last_name_prefix() {
+    let (app, pool) = setup_test().await;
+    let doctor = TestUser::create_active_user(&pool, &format!("doctor{}", unique_suffix()), "DoctorPass123!", false).await;
+    let doctor_token = login_and_get_token(&app, &doctor.username, "DoctorPass123!").await;
+
+    let _patient1 = create_test_patient(&app, &doctor_token, "Mario", "D'Angelo", None).await;
+    let _patient2 = create_test_patient(&app, &doctor_token, "Maria", "Bianchi", None).await;
+
+    // Case, apostrophes and accents are ignored
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/api/v1/patients/search?last_name=dang%C3%A8")
+                .header("authorization", format!("Bearer {}", doctor_token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = body_to_bytes(response.into_body()).await;
+    let json: Value = serde_json::from_slice(&body).unwrap();
+
+    let patients = json["patients"].as_array().unwrap();
+    assert_eq!(patients.len(), 1);
+    assert_eq!(patients[0]["last_name"], "D'Angelo");
+
+    teardown_test_db(&pool).await;
+}
+
 /// Test: Search patients by status filter
 #[tokio::test]
 #[cfg(feature = "rbac")]
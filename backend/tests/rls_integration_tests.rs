@@ -0,0 +1,168 @@
+/*!
+ * Request Transaction Integration Tests
+ *
+ * Integration tests for the RLS transaction shared by a request:
+ * - Service operations of one request share a transaction
+ * - Nested operations fall back to their own transaction
+ * - Dropped operations roll back only their own writes
+ * - Writes become visible when the request commits
+ * - A failed request rolls back every operation but detached ones
+ */
+
+use docpat_backend::db::rls::{begin_detached_with_rls, begin_with_rls, with_rls_scope};
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+mod test_utils;
+use test_utils::{teardown_test_db, TestApp, TestUser};
+
+const TEST_PASSWORD: &str = "SecurePass123!";
+
+/// Helper function to setup a clean database with one doctor
+async fn setup_test() -> (PgPool, Uuid) {
+    let (_app, pool) = TestApp::new().await;
+    teardown_test_db(&pool).await;
+
+    sqlx::query("DELETE FROM holidays")
+        .execute(&pool)
+        .await
+        .expect("Failed to clear holidays");
+
+    let doctor = TestUser::create_active_user(&pool, "rlsdoctor", TEST_PASSWORD, false).await;
+    (pool, doctor.id)
+}
+
+/// Identifier of the current database transaction
+async fn txid(conn: &mut PgConnection) -> i64 {
+    sqlx::query_scalar("SELECT txid_current()")
+        .fetch_one(conn)
+        .await
+        .unwrap()
+}
+
+/// Insert a holiday on day `day` of January 2030
+async fn insert_holiday(conn: &mut PgConnection, day: u32, user_id: Uuid) {
+    sqlx::query(
+        "INSERT INTO holidays (holiday_date, name, created_by) \
+         VALUES (make_date(2030, 1, $1), 'RLS probe', $2)",
+    )
+    .bind(day as i32)
+    .bind(user_id)
+    .execute(conn)
+    .await
+    .expect("Failed to insert holiday");
+}
+
+/// Days of the probe holidays visible to `executor`
+async fn holiday_days<'e, E>(executor: E) -> Vec<i32>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    sqlx::query_scalar(
+        "SELECT EXTRACT(DAY FROM holiday_date)::INT FROM holidays \
+         WHERE name = 'RLS probe' ORDER BY holiday_date",
+    )
+    .fetch_all(executor)
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn test_operations_share_the_request_transaction() {
+    let (pool, user_id) = setup_test().await;
+
+    let request_pool = pool.clone();
+    with_rls_scope(
+        user_id,
+        async move {
+            let pool = request_pool;
+            let mut first = begin_with_rls(&pool, user_id).await.unwrap();
+            let request_txid = txid(&mut first).await;
+            let role: String =
+                sqlx::query_scalar("SELECT current_setting('app.current_user_role')")
+                    .fetch_one(&mut *first)
+                    .await
+                    .unwrap();
+            assert_eq!(role, "DOCTOR");
+
+            // Started while `first` is still open: runs on its own
+            let mut nested = begin_with_rls(&pool, user_id).await.unwrap();
+            assert_ne!(txid(&mut nested).await, request_txid);
+            nested.commit().await.unwrap();
+            first.commit().await.unwrap();
+
+            let mut second = begin_with_rls(&pool, user_id).await.unwrap();
+            assert_eq!(txid(&mut second).await, request_txid);
+            second.commit().await.unwrap();
+        },
+        |_| true,
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn test_request_commits_only_committed_operations() {
+    let (pool, user_id) = setup_test().await;
+
+    let request_pool = pool.clone();
+    with_rls_scope(
+        user_id,
+        async move {
+            let pool = request_pool;
+            let mut tx = begin_with_rls(&pool, user_id).await.unwrap();
+            insert_holiday(&mut tx, 1, user_id).await;
+            tx.commit().await.unwrap();
+
+            // Dropped after a failed statement: only its own write is undone
+            let mut tx = begin_with_rls(&pool, user_id).await.unwrap();
+            insert_holiday(&mut tx, 2, user_id).await;
+            assert!(sqlx::query("SELECT 1 / 0").execute(&mut *tx).await.is_err());
+            drop(tx);
+
+            let mut tx = begin_with_rls(&pool, user_id).await.unwrap();
+            assert_eq!(holiday_days(&mut *tx).await, vec![1]);
+            insert_holiday(&mut tx, 3, user_id).await;
+            tx.commit().await.unwrap();
+
+            // Nothing is visible outside the request before it ends
+            assert!(holiday_days(&pool).await.is_empty());
+
+            // Never committed
+            let mut tx = begin_with_rls(&pool, user_id).await.unwrap();
+            insert_holiday(&mut tx, 4, user_id).await;
+        },
+        |_| true,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(holiday_days(&pool).await, vec![1, 3]);
+}
+
+#[tokio::test]
+async fn test_failed_request_keeps_only_detached_operations() {
+    let (pool, user_id) = setup_test().await;
+
+    let request_pool = pool.clone();
+    with_rls_scope(
+        user_id,
+        async move {
+            let pool = request_pool;
+            for day in [1, 2] {
+                let mut tx = begin_with_rls(&pool, user_id).await.unwrap();
+                insert_holiday(&mut tx, day, user_id).await;
+                tx.commit().await.unwrap();
+            }
+
+            let mut tx = begin_detached_with_rls(&pool, user_id).await.unwrap();
+            insert_holiday(&mut tx, 3, user_id).await;
+            tx.commit().await.unwrap();
+        },
+        |_| false,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(holiday_days(&pool).await, vec![3]);
+}
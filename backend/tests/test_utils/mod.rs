@@ -18,10 +18,13 @@ use std::sync::Arc;
 use docpat_backend::{
     config::{DatabaseConfig, JwtConfig, SecurityConfig},
     handlers::auth::AppState,
-    middleware::session_timeout::SessionManager,
+    middleware::{
+        ip_allowlist::AdminIpAllowlist, rate_limit::{LoginThrottle, RateLimitLayer}, session_timeout::SessionManager,
+        token_denylist::TokenDenylist,
+    },
     models::UserRole,
-    routes::create_api_v1_routes,
-    services::{AuthService, EmailService, SettingsService},
+    routes::{create_api_v1_routes, create_api_v2_routes},
+    services::{AuthService, EmailService, ReferenceCache, SettingsService},
     utils::{encryption::EncryptionKey, PasswordHasherUtil},
 };
 
@@ -69,6 +72,7 @@ impl TestApp {
             refresh_secret: "test_refresh_secret_key_minimum_32_characters_long".to_string(),
             access_token_expiry: 1800,
             refresh_token_expiry: 604800,
+            key_ring: None,
         };
 
         let security_config = SecurityConfig {
@@ -76,6 +80,7 @@ impl TestApp {
             session_timeout: 1800,
             max_failed_login_attempts: 5,
             lockout_duration: 900,
+            captcha: None,
         };
 
         // Create database pool
@@ -116,18 +121,26 @@ impl TestApp {
             pool: pool.clone(),
             auth_service,
             session_manager,
+            token_denylist: TokenDenylist::new(),
+            login_throttle: LoginThrottle::new(),
+            rate_limiter: RateLimitLayer::new(),
+            admin_ip_allowlist: AdminIpAllowlist::default(),
             encryption_key: Some(encryption_key),
             email_service: Some(email_service),
+            sistema_ts: None,
+            video_room_provider: None,
             settings_service,
+            reference_cache: ReferenceCache::memory(),
             start_time: std::time::SystemTime::now(),
             environment: "test".to_string(),
             #[cfg(feature = "rbac")]
             enforcer,
         };
 
-        // Create router
+        // Create router (both API versions, nested as in main.rs)
         let app = Router::new()
-            .nest("/api/v1", create_api_v1_routes(app_state));
+            .nest("/api/v1", create_api_v1_routes(app_state.clone()))
+            .nest("/api/v2", create_api_v2_routes(app_state));
 
         (app, pool)
     }
@@ -11,6 +11,7 @@
  * - Sign visit (POST /api/v1/visits/:id/sign)
  * - Lock visit (POST /api/v1/visits/:id/lock)
  * - Get statistics (GET /api/v1/visits/statistics)
+ * - Search visit notes (GET /api/v1/visits/search)
  * - SOAP note workflow (DRAFT → SIGNED → LOCKED)
  * - Data encryption/decryption round-trip
  * - RBAC permission enforcement
@@ -1273,6 +1274,89 @@ async fn test_data_encryption_round_trip() {
     assert_eq!(json["history_present_illness"], sensitive_data);
 }
 
+/// Test: Visit notes are found by full-text search, with matches highlighted
+#[tokio::test]
+#[cfg(feature = "rbac")]
+async fn test_search_visit_notes() {
+    let (app, pool) = setup_test().await;
+    let doctor = TestUser::create_active_user(&pool, &format!("doctor{}", unique_suffix()), "DoctorPass123!", false).await;
+    let doctor_token = login_and_get_token(&app, &doctor.username, "DoctorPass123!").await;
+
+    let patient = create_test_patient(&app, &doctor_token, "Search", "Test").await;
+    let patient_id = patient["id"].as_str().unwrap();
+
+    let visit = create_test_visit(&app, &doctor_token, patient_id, &doctor.id.to_string()).await;
+    let visit_id = visit["id"].as_str().unwrap();
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri(format!("/api/v1/visits/{}", visit_id))
+                .header("content-type", "application/json")
+                .header("authorization", format!("Bearer {}", doctor_token))
+                .body(Body::from(
+                    json!({
+                        "assessment": "Suspected Carpal Tunnel syndrome, right hand"
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let search = |q: &str| {
+        Request::builder()
+            .method("GET")
+            .uri(format!("/api/v1/visits/search?q={}&patient_id={}", q, patient_id))
+            .header("authorization", format!("Bearer {}", doctor_token))
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    // Case is ignored and every word must match
+    let response = app.clone().oneshot(search("carpal%20tunnel")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = body_to_bytes(response.into_body()).await;
+    let json: Value = serde_json::from_slice(&body).unwrap();
+
+    assert_eq!(json["total"], 1);
+    assert_eq!(json["results"][0]["visit_id"], visit_id);
+    assert_eq!(json["results"][0]["highlights"][0]["field"], "assessment");
+    assert_eq!(
+        json["results"][0]["highlights"][0]["snippet"],
+        "Suspected <mark>Carpal</mark> <mark>Tunnel</mark> syndrome, right hand"
+    );
+
+    let response = app.clone().oneshot(search("carpal%20migraine")).await.unwrap();
+    let body = body_to_bytes(response.into_body()).await;
+    let json: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["total"], 0);
+
+    // The index holds hashes, never the words themselves
+    let mut tx = pool.begin().await.unwrap();
+    sqlx::query("SELECT set_config('app.current_user_role', 'DOCTOR', true)")
+        .execute(&mut *tx)
+        .await
+        .unwrap();
+    let (vector,): (String,) = sqlx::query_as(
+        "SELECT search_vector::TEXT FROM visit_search_index WHERE visit_id = $1"
+    )
+    .bind(uuid::Uuid::parse_str(visit_id).unwrap())
+    .fetch_one(&mut *tx)
+    .await
+    .unwrap();
+    tx.commit().await.unwrap();
+    assert!(!vector.to_lowercase().contains("carpal"));
+
+    // A query without a usable word is rejected
+    let response = app.clone().oneshot(search("a")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
 // ============================================================================
 // COMPLETE SOAP NOTE WORKFLOW TEST
 // ============================================================================
@@ -372,8 +372,10 @@ export function AppointmentCalendar({
   // Get status label for the dialog
   const getStatusLabel = (status: AppointmentStatus): string => {
     const statusMap: Record<AppointmentStatus, string> = {
+      [AppointmentStatus.PENDING]: t('appointments.status.pending'),
       [AppointmentStatus.SCHEDULED]: t('appointments.status.scheduled'),
       [AppointmentStatus.CONFIRMED]: t('appointments.status.confirmed'),
+      [AppointmentStatus.ARRIVED]: t('appointments.status.arrived'),
       [AppointmentStatus.IN_PROGRESS]: t('appointments.status.in_progress'),
       [AppointmentStatus.COMPLETED]: t('appointments.status.completed'),
       [AppointmentStatus.CANCELLED]: t('appointments.status.cancelled'),
@@ -39,6 +39,7 @@ import { Alert, AlertDescription } from '../ui/alert';
 import { Button } from '../ui/button';
 import { Input } from '../ui/input';
 import { Label } from '../ui/label';
+import { Checkbox } from '../ui/checkbox';
 import { Textarea } from '../ui/textarea';
 import {
   Select,
@@ -95,6 +96,10 @@ const createAppointmentSchema = (t: (key: string) => string) => {
       recurring_end_date: z.date().optional(),
       recurring_max_occurrences: z.number().min(1).max(100).optional(),
       send_notification: z.boolean().optional(),
+      resource_ids: z
+        .array(z.string())
+        .max(5, t('appointments.validation.resources_max'))
+        .optional(),
     })
     .refine(
       (data) => {
@@ -152,6 +157,7 @@ export function AppointmentForm({
       recurring_max_occurrences:
         appointment?.recurring_pattern?.max_occurrences || undefined,
       send_notification: true, // Default to sending notification
+      resource_ids: [],
     },
   });
 
@@ -170,6 +176,7 @@ export function AppointmentForm({
   const selectedDuration = watch('duration_minutes');
   const selectedPatientId = watch('patient_id');
   const sendNotification = watch('send_notification');
+  const selectedResourceIds = watch('resource_ids') ?? [];
 
   // Get scheduling constraints (working hours, holidays)
   const {
@@ -194,10 +201,31 @@ export function AppointmentForm({
     ? format(startOfDay(selectedDate), "yyyy-MM-dd'T'HH:mm:ss'Z'")
     : '';
 
+  // Rooms and equipment that can be reserved with a new appointment
+  const { data: resources = [] } = useQuery({
+    queryKey: ['appointment-resources'],
+    queryFn: () => appointmentsApi.getResources(),
+    enabled: !isEditing,
+    staleTime: 5 * 60 * 1000,
+  });
+
   const { data: availability, isLoading: isCheckingAvailability } = useQuery({
-    queryKey: ['availability', providerId, dateString, selectedDuration],
+    queryKey: [
+      'availability',
+      providerId,
+      dateString,
+      selectedDuration,
+      selectedType,
+      selectedResourceIds,
+    ],
     queryFn: () =>
-      appointmentsApi.checkAvailability(providerId, dateString, selectedDuration),
+      appointmentsApi.checkAvailability(
+        providerId,
+        dateString,
+        selectedDuration,
+        selectedType,
+        selectedResourceIds
+      ),
     enabled: !!providerId && !!selectedDate && !!selectedTime && selectedDuration > 0,
     staleTime: 60 * 1000,
   });
@@ -232,6 +260,38 @@ export function AppointmentForm({
     });
   }, [availability, selectedTime, selectedDate, selectedDuration]);
 
+  /**
+   * Names of the selected resources reserved by other appointments at the
+   * selected time, so the warning can say why the slot is unavailable.
+   */
+  const reservedResourceNames = useMemo(() => {
+    if (!availability?.slots || !selectedTime) return [];
+
+    const [selectedHours, selectedMinutes] = selectedTime.split(':').map(Number);
+    const selectedStartMinutes = selectedHours * 60 + selectedMinutes;
+    const selectedEndMinutes = selectedStartMinutes + selectedDuration;
+
+    const reservedIds = new Set<string>();
+    availability.slots.forEach((slot) => {
+      const slotStartDate = new Date(slot.start);
+      const slotEndDate = new Date(slot.end);
+      const slotStartMinutes = slotStartDate.getUTCHours() * 60 + slotStartDate.getUTCMinutes();
+      const slotEndMinutes = slotEndDate.getUTCHours() * 60 + slotEndDate.getUTCMinutes();
+
+      if (slotStartMinutes < selectedEndMinutes && slotEndMinutes > selectedStartMinutes) {
+        slot.busy_resources?.forEach((id) => reservedIds.add(id));
+      }
+    });
+
+    return resources
+      .filter((resource) => reservedIds.has(resource.id))
+      .map((resource) => resource.name);
+  }, [availability, resources, selectedTime, selectedDuration]);
+
+  const bottleneckResource = resources.find(
+    (resource) => resource.id === availability?.bottleneck_resource_id
+  );
+
   // Track if submission should be blocked
   const [submissionBlocked, setSubmissionBlocked] = useState(false);
 
@@ -305,6 +365,7 @@ export function AppointmentForm({
             }
           : undefined,
       send_notification: data.send_notification,
+      resource_ids: data.resource_ids?.length ? data.resource_ids : undefined,
     };
 
     onSubmit(appointmentData);
@@ -604,6 +665,62 @@ export function AppointmentForm({
         </CardContent>
       </Card>
 
+      {/* Rooms & Equipment */}
+      {!isEditing && resources.length > 0 && (
+        <Card>
+          <CardHeader>
+            <CardTitle className="text-lg">
+              {t('appointments.resources.title')}
+            </CardTitle>
+          </CardHeader>
+          <CardContent className="space-y-3">
+            <Controller
+              name="resource_ids"
+              control={control}
+              render={({ field }) => (
+                <div className="grid gap-3 sm:grid-cols-2">
+                  {resources.map((resource) => {
+                    const checked = field.value?.includes(resource.id) ?? false;
+                    const typeLabel = t(
+                      `appointments.resources.type.${resource.resource_type.toLowerCase()}`
+                    );
+                    return (
+                      <div key={resource.id} className="flex items-center gap-2">
+                        <Checkbox
+                          id={`resource-${resource.id}`}
+                          checked={checked}
+                          onCheckedChange={(value) =>
+                            field.onChange(
+                              value
+                                ? [...(field.value ?? []), resource.id]
+                                : (field.value ?? []).filter((id) => id !== resource.id)
+                            )
+                          }
+                        />
+                        <Label htmlFor={`resource-${resource.id}`} className="font-normal">
+                          {resource.name}
+                          <span className="ml-1 text-muted-foreground">
+                            ({typeLabel})
+                          </span>
+                        </Label>
+                      </div>
+                    );
+                  })}
+                </div>
+              )}
+            />
+            {errors.resource_ids && (
+              <p className="text-sm text-destructive">{errors.resource_ids.message}</p>
+            )}
+            {bottleneckResource && (
+              <p className="text-sm text-muted-foreground">
+                {t('appointments.resources.bottleneck', { name: bottleneckResource.name })}
+              </p>
+            )}
+          </CardContent>
+        </Card>
+      )}
+
       {/* Recurring Appointment */}
       {!isEditing && (
         <Card>
@@ -779,7 +896,11 @@ export function AppointmentForm({
                 })
               : currentDateDisabledReason === 'non_working_day'
                 ? t('appointments.validation.non_working_day')
-                : t('appointments.validation.slot_unavailable')}
+                : reservedResourceNames.length > 0
+                  ? t('appointments.validation.resource_reserved', {
+                      names: reservedResourceNames.join(', '),
+                    })
+                  : t('appointments.validation.slot_unavailable')}
           </AlertDescription>
         </Alert>
       )}
@@ -0,0 +1,210 @@
+/**
+ * CalendarFeedCard Component
+ *
+ * Lets a provider subscribe their phone or desktop calendar to an iCalendar
+ * feed of their appointments. The feed URL carries a secret token, so it is
+ * shown only right after it is created; creating it again replaces the URL.
+ */
+
+import { useState } from 'react';
+import { useTranslation } from 'react-i18next';
+import { useMutation, useQuery, useQueryClient } from '@tanstack/react-query';
+import { AxiosError } from 'axios';
+import { CalendarDays, Check, Copy } from 'lucide-react';
+import { format, parseISO } from 'date-fns';
+
+import { Card, CardContent, CardDescription, CardHeader, CardTitle } from '@/components/ui/card';
+import { Button } from '@/components/ui/button';
+import { Input } from '@/components/ui/input';
+import { Label } from '@/components/ui/label';
+import {
+  Select,
+  SelectContent,
+  SelectItem,
+  SelectTrigger,
+  SelectValue,
+} from '@/components/ui/select';
+import { useToast } from '@/hooks/use-toast';
+import { apiClient } from '@/services/api/axios-instance';
+import { appointmentsApi } from '@/services/api/appointments';
+import type { CalendarPrivacyMode } from '@/types/appointment';
+
+const CALENDAR_FEED_QUERY_KEY = ['calendar-feed'];
+
+/**
+ * Absolute URL of a feed path, on the API host
+ */
+function feedUrl(path: string): string {
+  return new URL(path, apiClient.defaults.baseURL || window.location.origin).toString();
+}
+
+/**
+ * Calendar feed settings of the signed-in provider
+ */
+export function CalendarFeedCard() {
+  const { t } = useTranslation();
+  const { toast } = useToast();
+  const queryClient = useQueryClient();
+
+  const [privacyMode, setPrivacyMode] = useState<CalendarPrivacyMode>('BUSY_ONLY');
+  const [newUrl, setNewUrl] = useState<string | null>(null);
+  const [copied, setCopied] = useState(false);
+
+  const { data: feed, isLoading } = useQuery({
+    queryKey: CALENDAR_FEED_QUERY_KEY,
+    queryFn: appointmentsApi.getCalendarFeed,
+  });
+
+  const onError = (error: AxiosError<{ message?: string }>) => {
+    toast({
+      variant: 'destructive',
+      title: t('app.error'),
+      description: error.response?.data?.message || t('errors.generic'),
+    });
+  };
+
+  const createMutation = useMutation({
+    mutationFn: (mode: CalendarPrivacyMode) => appointmentsApi.createCalendarFeed(mode),
+    onSuccess: (created) => {
+      setNewUrl(feedUrl(created.path));
+      queryClient.invalidateQueries({ queryKey: CALENDAR_FEED_QUERY_KEY });
+    },
+    onError,
+  });
+
+  const updateMutation = useMutation({
+    mutationFn: (mode: CalendarPrivacyMode) => appointmentsApi.updateCalendarFeed(mode),
+    onSuccess: () => {
+      queryClient.invalidateQueries({ queryKey: CALENDAR_FEED_QUERY_KEY });
+      toast({ title: t('profile.calendarFeed.updated') });
+    },
+    onError,
+  });
+
+  const deleteMutation = useMutation({
+    mutationFn: () => appointmentsApi.deleteCalendarFeed(),
+    onSuccess: () => {
+      setNewUrl(null);
+      queryClient.invalidateQueries({ queryKey: CALENDAR_FEED_QUERY_KEY });
+      toast({ title: t('profile.calendarFeed.revoked') });
+    },
+    onError,
+  });
+
+  const currentMode = feed?.privacy_mode ?? privacyMode;
+
+  const handleModeChange = (mode: CalendarPrivacyMode) => {
+    if (feed) {
+      updateMutation.mutate(mode);
+    } else {
+      setPrivacyMode(mode);
+    }
+  };
+
+  const handleCopy = async () => {
+    if (!newUrl) return;
+    try {
+      await navigator.clipboard.writeText(newUrl);
+      setCopied(true);
+      toast({
+        title: t('common.copied'),
+        description: t('profile.calendarFeed.copied'),
+      });
+      setTimeout(() => setCopied(false), 2000);
+    } catch (error) {
+      console.error('Failed to copy:', error);
+    }
+  };
+
+  const isBusy =
+    createMutation.isPending || updateMutation.isPending || deleteMutation.isPending;
+
+  return (
+    <Card>
+      <CardHeader>
+        <CardTitle className="flex items-center gap-2">
+          <CalendarDays className="h-5 w-5" />
+          {t('profile.calendarFeed.title')}
+        </CardTitle>
+        <CardDescription>{t('profile.calendarFeed.description')}</CardDescription>
+      </CardHeader>
+
+      <CardContent className="space-y-4">
+        {/* Privacy Mode */}
+        <div className="space-y-2">
+          <Label htmlFor="calendar-feed-privacy">{t('profile.calendarFeed.privacy')}</Label>
+          <Select
+            value={currentMode}
+            onValueChange={(value) => handleModeChange(value as CalendarPrivacyMode)}
+            disabled={isLoading || isBusy}
+          >
+            <SelectTrigger id="calendar-feed-privacy" className="sm:w-72">
+              <SelectValue />
+            </SelectTrigger>
+            <SelectContent>
+              <SelectItem value="BUSY_ONLY">{t('profile.calendarFeed.busyOnly')}</SelectItem>
+              <SelectItem value="FULL">{t('profile.calendarFeed.full')}</SelectItem>
+            </SelectContent>
+          </Select>
+          <p className="text-sm text-muted-foreground">
+            {currentMode === 'FULL'
+              ? t('profile.calendarFeed.fullHint')
+              : t('profile.calendarFeed.busyOnlyHint')}
+          </p>
+        </div>
+
+        {/* New Feed URL (shown once) */}
+        {newUrl && (
+          <div className="space-y-2">
+            <Label htmlFor="calendar-feed-url">{t('profile.calendarFeed.url')}</Label>
+            <div className="flex gap-2">
+              <Input id="calendar-feed-url" value={newUrl} readOnly className="font-mono" />
+              <Button
+                type="button"
+                variant="outline"
+                size="icon"
+                onClick={handleCopy}
+                aria-label={t('common.copy')}
+              >
+                {copied ? <Check className="h-4 w-4" /> : <Copy className="h-4 w-4" />}
+              </Button>
+            </div>
+            <p className="text-sm text-muted-foreground">{t('profile.calendarFeed.urlHint')}</p>
+          </div>
+        )}
+
+        {/* Feed Status */}
+        {feed && (
+          <p className="text-sm text-muted-foreground">
+            {t('profile.calendarFeed.activeSince', {
+              date: format(parseISO(feed.created_at), 'PPp'),
+            })}
+            {feed.last_accessed_at &&
+              ` · ${t('profile.calendarFeed.lastSynced', {
+                date: format(parseISO(feed.last_accessed_at), 'PPp'),
+              })}`}
+          </p>
+        )}
+
+        <div className="flex flex-wrap gap-2">
+          <Button
+            onClick={() => createMutation.mutate(currentMode)}
+            disabled={isLoading || isBusy}
+          >
+            {feed ? t('profile.calendarFeed.regenerate') : t('profile.calendarFeed.create')}
+          </Button>
+          {feed && (
+            <Button
+              variant="outline"
+              onClick={() => deleteMutation.mutate()}
+              disabled={isBusy}
+              className="text-destructive hover:text-destructive"
+            >
+              {t('profile.calendarFeed.revoke')}
+            </Button>
+          )}
+        </div>
+      </CardContent>
+    </Card>
+  );
+}
@@ -62,9 +62,14 @@ export function PatientSearchCombobox({
     queryKey: ['patients', 'search', 'active', debouncedQuery],
     queryFn: () => {
       // Always filter for ACTIVE patients only - inactive patients cannot have appointments
-      // Use higher limit to show all patients (sorted alphabetically by backend)
+      if (debouncedQuery.length >= 2) {
+        return patientsApi.autocomplete({
+          q: debouncedQuery,
+          status: PatientStatus.ACTIVE,
+        });
+      }
+      // Without a query, list patients (sorted alphabetically by backend)
       return patientsApi.search({
-        query: debouncedQuery.length >= 2 ? debouncedQuery : undefined,
         status: PatientStatus.ACTIVE,
         limit: 100,
       });
@@ -0,0 +1,136 @@
+/**
+ * SchedulingConflictDialog Component
+ *
+ * Shown when the server refuses an appointment time (409
+ * SCHEDULING_CONFLICT). Lists why the time is not available and the
+ * nearest free times suggested by the server, each of which can be
+ * booked with one click. When the provider's overbooking policy allows
+ * it, the refused time can also be booked anyway over the appointments
+ * already there.
+ */
+
+import { useTranslation } from 'react-i18next';
+import { format, parseISO } from 'date-fns';
+import { AlertTriangle, Calendar, Clock } from 'lucide-react';
+
+import type { AlternativeSlot, SchedulingConflict } from '../../types/appointment';
+import {
+  AlertDialog,
+  AlertDialogCancel,
+  AlertDialogContent,
+  AlertDialogDescription,
+  AlertDialogFooter,
+  AlertDialogHeader,
+  AlertDialogTitle,
+} from '../ui/alert-dialog';
+import { Button } from '../ui/button';
+import { Separator } from '../ui/separator';
+
+interface SchedulingConflictDialogProps {
+  open: boolean;
+  onOpenChange: (open: boolean) => void;
+  conflicts: SchedulingConflict[];
+  alternatives: AlternativeSlot[];
+  onSelect: (slot: AlternativeSlot) => void;
+  /** Whether the refused time can be overbooked */
+  overbookable?: boolean;
+  /** Book the refused time anyway; the button is hidden when not given */
+  onOverbook?: () => void;
+  isSubmitting?: boolean;
+}
+
+/**
+ * SchedulingConflictDialog displays the reasons a time was refused and
+ * the alternatives to book instead.
+ */
+export function SchedulingConflictDialog({
+  open,
+  onOpenChange,
+  conflicts,
+  alternatives,
+  onSelect,
+  overbookable = false,
+  onOverbook,
+  isSubmitting = false,
+}: SchedulingConflictDialogProps) {
+  const { t } = useTranslation();
+
+  return (
+    <AlertDialog open={open} onOpenChange={onOpenChange}>
+      <AlertDialogContent className="max-w-md">
+        <AlertDialogHeader>
+          <AlertDialogTitle className="flex items-center gap-2 text-orange-600">
+            <AlertTriangle className="h-5 w-5" />
+            {t('appointments.scheduling_conflict.title')}
+          </AlertDialogTitle>
+          <AlertDialogDescription>
+            {t('appointments.scheduling_conflict.description')}
+          </AlertDialogDescription>
+        </AlertDialogHeader>
+
+        <div className="space-y-4">
+          {/* Reasons */}
+          <ul className="space-y-2">
+            {conflicts.map((conflict, index) => (
+              <li
+                key={conflict.appointment_id ?? `${conflict.kind}-${index}`}
+                className="rounded-md border border-destructive/20 bg-destructive/5 p-3 text-sm"
+              >
+                {conflict.message}
+              </li>
+            ))}
+          </ul>
+
+          <Separator />
+
+          {/* Alternatives */}
+          <div>
+            <p className="mb-2 text-sm font-medium">
+              {t('appointments.scheduling_conflict.alternatives')}
+            </p>
+            {alternatives.length === 0 ? (
+              <p className="text-sm text-muted-foreground">
+                {t('appointments.scheduling_conflict.no_alternatives')}
+              </p>
+            ) : (
+              <div className="space-y-2">
+                {alternatives.map((slot) => (
+                  <div
+                    key={slot.start}
+                    className="flex items-center justify-between rounded-md border p-3"
+                  >
+                    <div className="text-sm">
+                      <div className="flex items-center gap-2">
+                        <Calendar className="h-4 w-4 text-muted-foreground" />
+                        <span>{format(parseISO(slot.start), 'EEEE, MMMM d, yyyy')}</span>
+                      </div>
+                      <div className="mt-1 flex items-center gap-2">
+                        <Clock className="h-4 w-4 text-muted-foreground" />
+                        <span className="font-medium">
+                          {format(parseISO(slot.start), 'HH:mm')} -{' '}
+                          {format(parseISO(slot.end), 'HH:mm')}
+                        </span>
+                      </div>
+                    </div>
+                    <Button size="sm" disabled={isSubmitting} onClick={() => onSelect(slot)}>
+                      {t('appointments.scheduling_conflict.book')}
+                    </Button>
+                  </div>
+                ))}
+              </div>
+            )}
+          </div>
+        </div>
+
+        <AlertDialogFooter>
+          <AlertDialogCancel>{t('common.cancel')}</AlertDialogCancel>
+          {overbookable && onOverbook && (
+            <Button variant="destructive" disabled={isSubmitting} onClick={onOverbook}>
+              {t('appointments.scheduling_conflict.overbook')}
+            </Button>
+          )}
+        </AlertDialogFooter>
+      </AlertDialogContent>
+    </AlertDialog>
+  );
+}
@@ -118,6 +118,7 @@ vi.mock('@/services/api/appointments', () => ({
         { start: '2026-01-20T14:00:00Z', end: '2026-01-20T18:00:00Z', available: true },
       ],
     }),
+    getResources: vi.fn().mockResolvedValue([]),
   },
 }));
 
@@ -17,6 +17,7 @@ import type { Patient } from '@/types/patient';
 vi.mock('@/services/api/patients', () => ({
   patientsApi: {
     search: vi.fn(),
+    autocomplete: vi.fn(),
     getById: vi.fn(),
   },
 }));
@@ -89,6 +90,10 @@ describe('PatientSearchCombobox', () => {
       patients: mockPatients,
       total: 2,
     });
+    vi.mocked(patientsApi.autocomplete).mockResolvedValue({
+      patients: [mockPatients[0]],
+      total: 1,
+    });
     vi.mocked(patientsApi.getById).mockResolvedValue(mockPatients[0]);
   });
 
@@ -212,13 +217,14 @@ describe('PatientSearchCombobox', () => {
 
       // Should debounce the search
       await waitFor(() => {
-        expect(patientsApi.search).toHaveBeenCalledWith(
-          expect.objectContaining({
-            query: 'John',
-            status: PatientStatus.ACTIVE,
-          })
-        );
+        expect(patientsApi.autocomplete).toHaveBeenCalledWith({
+          q: 'John',
+          status: PatientStatus.ACTIVE,
+        });
       }, { timeout: 500 });
+      expect(patientsApi.search).not.toHaveBeenCalledWith(
+        expect.objectContaining({ query: 'John' })
+      );
     });
 
     it('should show no results message when search returns empty', async () => {
@@ -0,0 +1,128 @@
+/**
+ * SchedulingConflictDialog Component Tests
+ */
+
+import { describe, it, expect, vi } from 'vitest';
+import { render, screen } from '@testing-library/react';
+import userEvent from '@testing-library/user-event';
+import { SchedulingConflictDialog } from '../SchedulingConflictDialog';
+import type { AlternativeSlot, SchedulingConflict } from '@/types/appointment';
+
+// Mock i18next
+vi.mock('react-i18next', () => ({
+  useTranslation: () => ({
+    t: (key: string) => {
+      const translations: Record<string, string> = {
+        'appointments.scheduling_conflict.title': 'Time Not Available',
+        'appointments.scheduling_conflict.description':
+          'The appointment cannot be booked at this time.',
+        'appointments.scheduling_conflict.alternatives': 'Nearest free times',
+        'appointments.scheduling_conflict.no_alternatives': 'No free time was found nearby.',
+        'appointments.scheduling_conflict.book': 'Book',
+        'appointments.scheduling_conflict.overbook': 'Overbook anyway',
+        'common.cancel': 'Cancel',
+      };
+      return translations[key] || key;
+    },
+  }),
+}));
+
+const conflicts: SchedulingConflict[] = [
+  {
+    kind: 'OVERLAP',
+    message: 'Scheduling conflict detected for provider at this time',
+    start: '2026-05-11T08:45:00Z',
+    end: '2026-05-11T09:15:00Z',
+    appointment_id: 'appointment-1',
+    resource_id: null,
+  },
+];
+
+const alternatives: AlternativeSlot[] = [
+  { start: '2026-05-11T08:00:00Z', end: '2026-05-11T08:30:00Z' },
+  { start: '2026-05-11T09:30:00Z', end: '2026-05-11T10:00:00Z' },
+];
+
+describe('SchedulingConflictDialog', () => {
+  it('lists the conflicts and the alternatives', () => {
+    render(
+      <SchedulingConflictDialog
+        open={true}
+        onOpenChange={vi.fn()}
+        conflicts={conflicts}
+        alternatives={alternatives}
+        onSelect={vi.fn()}
+      />
+    );
+
+    expect(screen.getByText('Time Not Available')).toBeInTheDocument();
+    expect(
+      screen.getByText('Scheduling conflict detected for provider at this time')
+    ).toBeInTheDocument();
+    expect(screen.getAllByRole('button', { name: 'Book' })).toHaveLength(2);
+  });
+
+  it('books the selected alternative', async () => {
+    const user = userEvent.setup();
+    const onSelect = vi.fn();
+
+    render(
+      <SchedulingConflictDialog
+        open={true}
+        onOpenChange={vi.fn()}
+        conflicts={conflicts}
+        alternatives={alternatives}
+        onSelect={onSelect}
+      />
+    );
+
+    await user.click(screen.getAllByRole('button', { name: 'Book' })[1]);
+    expect(onSelect).toHaveBeenCalledWith(alternatives[1]);
+  });
+
+  it('explains when there is no alternative', () => {
+    render(
+      <SchedulingConflictDialog
+        open={true}
+        onOpenChange={vi.fn()}
+        conflicts={conflicts}
+        alternatives={[]}
+        onSelect={vi.fn()}
+      />
+    );
+
+    expect(screen.getByText('No free time was found nearby.')).toBeInTheDocument();
+    expect(screen.queryByRole('button', { name: 'Book' })).not.toBeInTheDocument();
+  });
+
+  it('offers to overbook only when the time is overbookable', async () => {
+    const user = userEvent.setup();
+    const onOverbook = vi.fn();
+
+    const { rerender } = render(
+      <SchedulingConflictDialog
+        open={true}
+        onOpenChange={vi.fn()}
+        conflicts={conflicts}
+        alternatives={alternatives}
+        onSelect={vi.fn()}
+        onOverbook={onOverbook}
+      />
+    );
+    expect(screen.queryByRole('button', { name: 'Overbook anyway' })).not.toBeInTheDocument();
+
+    rerender(
+      <SchedulingConflictDialog
+        open={true}
+        onOpenChange={vi.fn()}
+        conflicts={conflicts}
+        alternatives={alternatives}
+        onSelect={vi.fn()}
+        overbookable={true}
+        onOverbook={onOverbook}
+      />
+    );
+    await user.click(screen.getByRole('button', { name: 'Overbook anyway' }));
+    expect(onOverbook).toHaveBeenCalledTimes(1);
+  });
+});
@@ -10,7 +10,9 @@ export * from './AppointmentForm';
 export * from './PatientSearchCombobox';
 export * from './DailyScheduleWidget';
 export * from './ConflictWarningDialog';
+export * from './SchedulingConflictDialog';
 export * from './QuickRescheduleDialog';
 export * from './AvailabilityIndicator';
 export * from './PrintScheduleButton';
 export * from './NotificationOptions';
+export * from './CalendarFeedCard';
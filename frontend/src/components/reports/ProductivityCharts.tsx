@@ -26,6 +26,7 @@ import {
   Users,
   TrendingUp,
   CheckCircle2,
+  Hourglass,
 } from 'lucide-react';
 
 import { Card, CardContent, CardDescription, CardHeader, CardTitle } from '@/components/ui/card';
@@ -157,7 +158,7 @@ export function ProductivityCharts({ data, isLoading }: ProductivityChartsProps)
     return (
       <div className="space-y-6">
         <div className="grid gap-4 md:grid-cols-3 lg:grid-cols-6">
-          {[1, 2, 3, 4, 5, 6].map((i) => (
+          {[1, 2, 3, 4, 5, 6, 7].map((i) => (
             <Card key={i}>
               <CardHeader className="pb-2">
                 <Skeleton className="h-4 w-20" />
@@ -232,6 +233,12 @@ export function ProductivityCharts({ data, isLoading }: ProductivityChartsProps)
           icon={Clock}
           iconColor="text-muted-foreground"
         />
+        <SummaryCard
+          title={t('reports.productivity.avgWait')}
+          value={`${summary.avg_wait_minutes.toFixed(0)} min`}
+          icon={Hourglass}
+          iconColor="text-orange-500"
+        />
       </div>
 
       {/* Provider Comparison Chart */}
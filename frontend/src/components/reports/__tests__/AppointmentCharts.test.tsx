@@ -58,6 +58,7 @@ const createMockData = (overrides?: Partial<AppointmentUtilizationReport>): Appo
     { date: '2024-01-02', scheduled: 6, completed: 5, cancelled: 0, no_shows: 1 },
     { date: '2024-01-03', scheduled: 4, completed: 4, cancelled: 0, no_shows: 0 },
   ],
+  data_as_of: null,
   ...overrides,
 });
 
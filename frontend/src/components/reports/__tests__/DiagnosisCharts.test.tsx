@@ -47,6 +47,7 @@ const createMockData = (overrides?: Partial<DiagnosisTrendsReport>): DiagnosisTr
     { category: 'M00-M99', category_name: 'Musculoskeletal', count: 50 },
     { category: 'K00-K93', category_name: 'Digestive', count: 42 },
   ],
+  data_as_of: null,
   ...overrides,
 });
 
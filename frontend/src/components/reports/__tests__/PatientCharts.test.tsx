@@ -39,18 +39,19 @@ const createMockData = (overrides?: Partial<PatientStatisticsReport>): PatientSt
     unspecified: 10,
   },
   age_distribution: [
-    { age_group: '0-18', count: 50 },
-    { age_group: '19-30', count: 80 },
-    { age_group: '31-45', count: 120 },
-    { age_group: '46-60', count: 130 },
-    { age_group: '61-75', count: 80 },
-    { age_group: '76+', count: 40 },
+    { age_group: '0-18', count: 50, male: 25, female: 25 },
+    { age_group: '19-30', count: 80, male: 40, female: 40 },
+    { age_group: '31-45', count: 120, male: 60, female: 60 },
+    { age_group: '46-60', count: 130, male: 65, female: 65 },
+    { age_group: '61-75', count: 80, male: 40, female: 40 },
+    { age_group: '76+', count: 40, male: 20, female: 20 },
   ],
   monthly_registrations: [
     { year: 2024, month: 1, month_name: 'January', count: 10 },
     { year: 2024, month: 2, month_name: 'February', count: 8 },
     { year: 2024, month: 3, month_name: 'March', count: 12 },
   ],
+  data_as_of: null,
   ...overrides,
 });
 
@@ -31,6 +31,8 @@ const createMockData = (overrides?: Partial<ProviderProductivityReport>): Provid
     total_prescriptions: 85,
     total_documents: 45,
     avg_appointment_duration: 25,
+    avg_wait_minutes: 12,
+    median_wait_minutes: 10,
   },
   by_provider: [
     {
@@ -44,6 +46,7 @@ const createMockData = (overrides?: Partial<ProviderProductivityReport>): Provid
       unique_patients_seen: 60,
       avg_visits_per_day: 3.5,
       completion_rate: 95,
+      avg_wait_minutes: 14,
     },
     {
       provider_id: 'provider-2',
@@ -56,6 +59,7 @@ const createMockData = (overrides?: Partial<ProviderProductivityReport>): Provid
       unique_patients_seen: 45,
       avg_visits_per_day: 2.8,
       completion_rate: 88,
+      avg_wait_minutes: 9,
     },
   ],
   ...overrides,
@@ -71,7 +75,7 @@ describe('ProductivityCharts', () => {
       expect(skeletons.length).toBeGreaterThan(0);
     });
 
-    it('renders 6 summary stat skeletons', () => {
+    it('renders 7 summary stat skeletons', () => {
       render(<ProductivityCharts isLoading />);
 
       // Should have skeleton cards
@@ -130,6 +134,13 @@ describe('ProductivityCharts', () => {
       expect(screen.getByText('reports.productivity.avgDuration')).toBeInTheDocument();
       expect(screen.getByText('25 min')).toBeInTheDocument();
     });
+
+    it('displays average wait stat card', () => {
+      render(<ProductivityCharts data={createMockData()} />);
+
+      expect(screen.getByText('reports.productivity.avgWait')).toBeInTheDocument();
+      expect(screen.getByText('12 min')).toBeInTheDocument();
+    });
   });
 
   describe('Provider Comparison Chart', () => {
@@ -8,7 +8,7 @@
 
 import { describe, it, expect, vi } from 'vitest';
 import { AxiosError, AxiosHeaders } from 'axios';
-import { extractErrorMessage, getErrorTitle } from '../error-utils';
+import { extractErrorMessage, getErrorTitle, getSchedulingConflict } from '../error-utils';
 
 /** Mock translation function that returns the key itself */
 const mockT = vi.fn((key: string) => key) as unknown as import('i18next').TFunction;
@@ -164,3 +164,27 @@ describe('getErrorTitle', () => {
     expect(getErrorTitle('string error')).toBe('errors.title.unknownError');
   });
 });
+
+describe('getSchedulingConflict', () => {
+  it('returns the conflicts and alternatives of a scheduling conflict', () => {
+    const error = createAxiosError(409, 'Scheduling conflict detected for provider at this time');
+    error.response!.data = {
+      error: 'SCHEDULING_CONFLICT',
+      message: 'Scheduling conflict detected for provider at this time',
+      conflicts: [{ kind: 'OVERLAP', message: 'Scheduling conflict', start: null, end: null, appointment_id: 'a1', resource_id: null }],
+      alternatives: [{ start: '2026-05-11T08:00:00Z', end: '2026-05-11T08:30:00Z' }],
+      overbookable: true,
+    } as never;
+
+    const conflict = getSchedulingConflict(error);
+    expect(conflict?.conflicts[0].kind).toBe('OVERLAP');
+    expect(conflict?.alternatives).toHaveLength(1);
+    expect(conflict?.overbookable).toBe(true);
+  });
+
+  it('returns null for other conflicts and errors', () => {
+    expect(getSchedulingConflict(createAxiosError(409, 'Duplicate'))).toBeNull();
+    expect(getSchedulingConflict(createAxiosError(400, 'Bad request'))).toBeNull();
+    expect(getSchedulingConflict(new Error('boom'))).toBeNull();
+  });
+});
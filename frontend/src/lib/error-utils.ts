@@ -9,6 +9,8 @@
 import axios from 'axios';
 import type { TFunction } from 'i18next';
 
+import type { SchedulingConflictResponse } from '@/types/appointment';
+
 /**
  * HTTP status code to i18n error key mapping
  */
@@ -105,3 +107,26 @@ export function getErrorTitle(error: unknown): string {
 
   return 'errors.title.unknownError';
 }
+
+/**
+ * Returns the conflicts and suggested alternatives of an appointment
+ * time the server refused (409 `SCHEDULING_CONFLICT`), or null for any
+ * other error.
+ *
+ * @param error - The caught error
+ * @returns The refusal body, or null
+ */
+export function getSchedulingConflict(error: unknown): SchedulingConflictResponse | null {
+  if (axios.isAxiosError(error) && error.response?.status === 409) {
+    const data = error.response.data;
+    if (data?.error === 'SCHEDULING_CONFLICT' && Array.isArray(data.conflicts)) {
+      return {
+        ...data,
+        alternatives: Array.isArray(data.alternatives) ? data.alternatives : [],
+        overbookable: data.overbookable === true,
+      } as SchedulingConflictResponse;
+    }
+  }
+
+  return null;
+}
@@ -5,6 +5,7 @@
  * Allows users to:
  * - View their profile details
  * - Setup or disable MFA
+ * - Subscribe their calendar to their appointments (providers)
  * - Change password (future enhancement)
  */
 
@@ -49,6 +50,7 @@ import {
 import { useToast } from '@/hooks/use-toast';
 import { useAuthStore } from '@/store/authStore';
 import { MFASetup } from '@/components/auth/MFASetup';
+import { CalendarFeedCard } from '@/components/appointments/CalendarFeedCard';
 import { cn } from '@/lib/utils';
 
 /**
@@ -321,6 +323,9 @@ export function ProfilePage() {
         </CardContent>
       </Card>
 
+      {/* Calendar Feed Card (providers) */}
+      {(user.role === 'DOCTOR' || user.role === 'ADMIN') && <CalendarFeedCard />}
+
       {/* MFA Setup Dialog */}
       <Dialog open={showMfaSetup} onOpenChange={handleMfaDialogOpenChange}>
         <DialogContent className="sm:max-w-lg">
@@ -5,6 +5,7 @@
  * - Profile information display
  * - Security settings
  * - MFA setup/disable
+ * - Calendar feed card for providers
  */
 
 import { describe, it, expect, vi, beforeEach } from 'vitest';
@@ -39,6 +40,11 @@ vi.mock('@/hooks/use-toast', () => ({
   }),
 }));
 
+// Mock the calendar feed card (has its own tests)
+vi.mock('@/components/appointments/CalendarFeedCard', () => ({
+  CalendarFeedCard: () => <div data-testid="calendar-feed-card" />,
+}));
+
 // Mock useNavigate and useSearchParams
 const mockNavigate = vi.fn();
 const mockSetSearchParams = vi.fn();
@@ -175,4 +181,32 @@ describe('ProfilePage', () => {
       expect(screen.getByText('Admin User')).toBeInTheDocument();
     });
   });
+
+  describe('Calendar Feed', () => {
+    it('should show the calendar feed card to providers', () => {
+      renderWithProviders(<ProfilePage />, { withRouter: true });
+
+      expect(screen.getByTestId('calendar-feed-card')).toBeInTheDocument();
+    });
+
+    it('should not show the calendar feed card to other roles', () => {
+      vi.mocked(useAuthStore).mockReturnValue({
+        user: {
+          id: '2',
+          username: 'nurse',
+          first_name: 'Nina',
+          last_name: 'Nurse',
+          email: 'nurse@example.com',
+          role: 'NURSE',
+          status: 'ACTIVE',
+          mfa_enabled: true,
+        },
+        updateUser: mockUpdateUser,
+      } as any);
+
+      renderWithProviders(<ProfilePage />, { withRouter: true });
+
+      expect(screen.queryByTestId('calendar-feed-card')).not.toBeInTheDocument();
+    });
+  });
 });
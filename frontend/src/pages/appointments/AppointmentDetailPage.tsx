@@ -25,6 +25,7 @@ import {
   Mail,
   MessageSquare,
   Phone,
+  LogIn,
 } from 'lucide-react';
 
 import { extractErrorMessage, getErrorTitle } from '@/lib/error-utils';
@@ -125,6 +126,26 @@ export function AppointmentDetailPage() {
     },
   });
 
+  // Check-in mutation
+  const checkInMutation = useMutation({
+    mutationFn: (appointmentId: string) => appointmentsApi.checkIn(appointmentId),
+    onSuccess: () => {
+      queryClient.invalidateQueries({ queryKey: ['appointment', id] });
+      queryClient.invalidateQueries({ queryKey: ['appointments'] });
+      toast({
+        title: t('appointments.messages.status_updated'),
+        variant: 'default',
+      });
+    },
+    onError: (error: unknown) => {
+      toast({
+        title: t(getErrorTitle(error)),
+        description: extractErrorMessage(error, t),
+        variant: 'destructive',
+      });
+    },
+  });
+
   // Cancel appointment mutation
   const cancelMutation = useMutation({
     mutationFn: ({ appointmentId, reason, sendNotification }: { appointmentId: string; reason: string; sendNotification?: boolean }) =>
@@ -523,7 +544,21 @@ export function AppointmentDetailPage() {
                     </AlertDialog>
                   )}
 
-                {appointment.status === AppointmentStatus.CONFIRMED &&
+                {canTransitionStatus(appointment.status, AppointmentStatus.ARRIVED) &&
+                  appointment.status !== AppointmentStatus.ARRIVED && (
+                    <Button
+                      className="w-full justify-start"
+                      variant="outline"
+                      onClick={() => checkInMutation.mutate(appointment.id)}
+                      disabled={checkInMutation.isPending}
+                    >
+                      <LogIn className="mr-2 h-4 w-4 text-teal-600" />
+                      {t('appointments.actions.check_in')}
+                    </Button>
+                  )}
+
+                {(appointment.status === AppointmentStatus.CONFIRMED ||
+                  appointment.status === AppointmentStatus.ARRIVED) &&
                   canTransitionStatus(
                     appointment.status,
                     AppointmentStatus.IN_PROGRESS
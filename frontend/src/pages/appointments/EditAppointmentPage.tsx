@@ -5,6 +5,7 @@
  * and provides the form for updating appointment details.
  */
 
+import { useState } from 'react';
 import { useParams, useNavigate } from 'react-router-dom';
 import { useQuery, useMutation, useQueryClient } from '@tanstack/react-query';
 import { useTranslation } from 'react-i18next';
@@ -12,13 +13,17 @@ import { ArrowLeft, Calendar, AlertTriangle } from 'lucide-react';
 import { PageSpinner } from '../../components/ui/spinner';
 
 import { appointmentsApi } from '../../services/api/appointments';
-import { AppointmentForm } from '../../components/appointments';
-import type { UpdateAppointmentRequest } from '../../types/appointment';
+import { AppointmentForm, SchedulingConflictDialog } from '../../components/appointments';
+import type {
+  AlternativeSlot,
+  SchedulingConflictResponse,
+  UpdateAppointmentRequest,
+} from '../../types/appointment';
 import { AppointmentStatus } from '../../types/appointment';
 import { Button } from '../../components/ui/button';
 import { Card, CardContent, CardDescription, CardHeader, CardTitle } from '../../components/ui/card';
 import { useToast } from '../../hooks/use-toast';
-import { extractErrorMessage, getErrorTitle } from '@/lib/error-utils';
+import { extractErrorMessage, getErrorTitle, getSchedulingConflict } from '@/lib/error-utils';
 
 /**
  * EditAppointmentPage allows editing of existing appointments.
@@ -31,6 +36,12 @@ export function EditAppointmentPage() {
   const queryClient = useQueryClient();
   const { toast } = useToast();
 
+  // Last submitted changes and, when the new time was refused, why and the
+  // alternatives suggested by the server
+  const [lastRequest, setLastRequest] = useState<UpdateAppointmentRequest | null>(null);
+  const [schedulingConflict, setSchedulingConflict] =
+    useState<SchedulingConflictResponse | null>(null);
+
   // Fetch appointment data
   const {
     data: appointment,
@@ -61,6 +72,12 @@ export function EditAppointmentPage() {
       navigate(`/appointments/${id}`);
     },
     onError: (error: unknown) => {
+      const conflict = getSchedulingConflict(error);
+      if (conflict) {
+        setSchedulingConflict(conflict);
+        return;
+      }
+
       toast({
         variant: 'destructive',
         title: t(getErrorTitle(error)),
@@ -73,9 +90,29 @@ export function EditAppointmentPage() {
    * Handles form submission for updating the appointment.
    */
   const handleSubmit = (data: UpdateAppointmentRequest) => {
+    setLastRequest(data);
     updateMutation.mutate(data);
   };
 
+  /**
+   * Saves the last submitted changes at a suggested alternative time.
+   */
+  const handleSelectAlternative = (slot: AlternativeSlot) => {
+    if (!lastRequest) return;
+    setSchedulingConflict(null);
+    handleSubmit({ ...lastRequest, scheduled_start: slot.start });
+  };
+
+  /**
+   * Saves the last submitted changes at the refused time, over the
+   * appointments already there.
+   */
+  const handleOverbook = () => {
+    if (!lastRequest) return;
+    setSchedulingConflict(null);
+    handleSubmit({ ...lastRequest, overbook: true });
+  };
+
   /**
    * Navigates back to appointment detail page.
    */
@@ -171,6 +208,17 @@ export function EditAppointmentPage() {
           />
         </CardContent>
       </Card>
+
+      <SchedulingConflictDialog
+        open={schedulingConflict !== null}
+        onOpenChange={(open) => !open && setSchedulingConflict(null)}
+        conflicts={schedulingConflict?.conflicts ?? []}
+        alternatives={schedulingConflict?.alternatives ?? []}
+        onSelect={handleSelectAlternative}
+        overbookable={schedulingConflict?.overbookable ?? false}
+        onOverbook={handleOverbook}
+        isSubmitting={updateMutation.isPending}
+      />
     </div>
   );
 }
@@ -5,6 +5,7 @@
  * and handles API submission with success/error feedback.
  */
 
+import { useState } from 'react';
 import { useNavigate, useSearchParams } from 'react-router-dom';
 import { useMutation, useQueryClient } from '@tanstack/react-query';
 import { useTranslation } from 'react-i18next';
@@ -12,13 +13,17 @@ import { ArrowLeft, Calendar, CheckCircle } from 'lucide-react';
 import { parse, isValid } from 'date-fns';
 
 import { appointmentsApi } from '../../services/api/appointments';
-import { AppointmentForm } from '../../components/appointments';
-import type { CreateAppointmentRequest } from '../../types/appointment';
+import { AppointmentForm, SchedulingConflictDialog } from '../../components/appointments';
+import type {
+  AlternativeSlot,
+  CreateAppointmentRequest,
+  SchedulingConflictResponse,
+} from '../../types/appointment';
 import { useAuth } from '../../store/authStore';
 import { Button } from '../../components/ui/button';
 import { Card, CardContent, CardDescription, CardHeader, CardTitle } from '../../components/ui/card';
 import { useToast } from '../../hooks/use-toast';
-import { extractErrorMessage, getErrorTitle } from '@/lib/error-utils';
+import { extractErrorMessage, getErrorTitle, getSchedulingConflict } from '@/lib/error-utils';
 
 /**
  * NewAppointmentPage provides the interface for scheduling new appointments.
@@ -43,6 +48,12 @@ export function NewAppointmentPage() {
   const defaultDate = parsedDate && isValid(parsedDate) ? parsedDate : new Date();
   const defaultTime = defaultTimeParam || '09:00';
 
+  // Last submitted request and, when its time was refused, why and the
+  // alternatives suggested by the server
+  const [lastRequest, setLastRequest] = useState<CreateAppointmentRequest | null>(null);
+  const [schedulingConflict, setSchedulingConflict] =
+    useState<SchedulingConflictResponse | null>(null);
+
   // Create appointment mutation
   const createMutation = useMutation({
     mutationFn: (data: CreateAppointmentRequest) => appointmentsApi.create(data),
@@ -60,6 +71,12 @@ export function NewAppointmentPage() {
       navigate(`/appointments/${newAppointment.id}`);
     },
     onError: (error: unknown) => {
+      const conflict = getSchedulingConflict(error);
+      if (conflict) {
+        setSchedulingConflict(conflict);
+        return;
+      }
+
       toast({
         variant: 'destructive',
         title: t(getErrorTitle(error)),
@@ -72,9 +89,29 @@ export function NewAppointmentPage() {
    * Handles form submission for creating a new appointment.
    */
   const handleSubmit = (data: CreateAppointmentRequest) => {
+    setLastRequest(data);
     createMutation.mutate(data);
   };
 
+  /**
+   * Books the last submitted appointment at a suggested alternative time.
+   */
+  const handleSelectAlternative = (slot: AlternativeSlot) => {
+    if (!lastRequest) return;
+    setSchedulingConflict(null);
+    handleSubmit({ ...lastRequest, scheduled_start: slot.start });
+  };
+
+  /**
+   * Books the last submitted appointment at the refused time, over the
+   * appointments already there.
+   */
+  const handleOverbook = () => {
+    if (!lastRequest) return;
+    setSchedulingConflict(null);
+    handleSubmit({ ...lastRequest, overbook: true });
+  };
+
   /**
    * Navigates back to the appointments calendar.
    */
@@ -132,6 +169,17 @@ export function NewAppointmentPage() {
           />
         </CardContent>
       </Card>
+
+      <SchedulingConflictDialog
+        open={schedulingConflict !== null}
+        onOpenChange={(open) => !open && setSchedulingConflict(null)}
+        conflicts={schedulingConflict?.conflicts ?? []}
+        alternatives={schedulingConflict?.alternatives ?? []}
+        onSelect={handleSelectAlternative}
+        overbookable={schedulingConflict?.overbookable ?? false}
+        onOverbook={handleOverbook}
+        isSubmitting={createMutation.isPending}
+      />
     </div>
   );
 }
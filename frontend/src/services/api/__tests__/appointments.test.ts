@@ -263,6 +263,30 @@ describe('appointmentsApi', () => {
     });
   });
 
+  describe('checkIn', () => {
+    it('should check the patient in now by default', async () => {
+      const arrivedAppointment = { ...mockAppointment, status: 'ARRIVED' as const };
+      vi.mocked(apiClient.post).mockResolvedValue({ data: arrivedAppointment });
+
+      const result = await appointmentsApi.checkIn('appointment-1');
+
+      expect(apiClient.post).toHaveBeenCalledWith('/api/v1/appointments/appointment-1/check-in', {
+        arrived_at: undefined,
+      });
+      expect(result.status).toBe('ARRIVED');
+    });
+
+    it('should send the arrival time when given', async () => {
+      vi.mocked(apiClient.post).mockResolvedValue({ data: mockAppointment });
+
+      await appointmentsApi.checkIn('appointment-1', '2026-05-07T07:18:00Z');
+
+      expect(apiClient.post).toHaveBeenCalledWith('/api/v1/appointments/appointment-1/check-in', {
+        arrived_at: '2026-05-07T07:18:00Z',
+      });
+    });
+  });
+
   describe('checkAvailability', () => {
     it('should check availability successfully', async () => {
       vi.mocked(apiClient.get).mockResolvedValue({ data: mockAvailability });
@@ -379,4 +403,41 @@ describe('appointmentsApi', () => {
       expect(result).toEqual(mockAppointmentList);
     });
   });
+
+  describe('calendar feed', () => {
+    it('should return null when the user has no feed', async () => {
+      vi.mocked(apiClient.get).mockRejectedValue({ isAxiosError: true, response: { status: 404 } });
+
+      const result = await appointmentsApi.getCalendarFeed();
+
+      expect(apiClient.get).toHaveBeenCalledWith('/api/v1/appointments/calendar-feed');
+      expect(result).toBeNull();
+    });
+
+    it('should rethrow other errors', async () => {
+      vi.mocked(apiClient.get).mockRejectedValue({ isAxiosError: true, response: { status: 500 } });
+
+      await expect(appointmentsApi.getCalendarFeed()).rejects.toBeDefined();
+    });
+
+    it('should create the feed with the privacy mode', async () => {
+      const created = {
+        provider_id: 'provider-1',
+        privacy_mode: 'BUSY_ONLY',
+        last_accessed_at: null,
+        created_at: '2026-05-12T07:30:00Z',
+        updated_at: '2026-05-12T07:30:00Z',
+        token: 'abc',
+        path: '/api/v1/calendar/abc.ics',
+      };
+      vi.mocked(apiClient.post).mockResolvedValue({ data: created });
+
+      const result = await appointmentsApi.createCalendarFeed('BUSY_ONLY');
+
+      expect(apiClient.post).toHaveBeenCalledWith('/api/v1/appointments/calendar-feed', {
+        privacy_mode: 'BUSY_ONLY',
+      });
+      expect(result).toEqual(created);
+    });
+  });
 });
@@ -152,6 +152,19 @@ describe('patientsApi', () => {
     });
   });
 
+  describe('autocomplete', () => {
+    it('should autocomplete patients', async () => {
+      vi.mocked(apiClient.get).mockResolvedValue({ data: mockPatientList });
+
+      const result = await patientsApi.autocomplete({ q: 'ross', limit: 5 });
+
+      expect(apiClient.get).toHaveBeenCalledWith('/api/v1/patients/autocomplete', {
+        params: { q: 'ross', limit: 5 },
+      });
+      expect(result).toEqual(mockPatientList);
+    });
+  });
+
   describe('getById', () => {
     it('should fetch patient by ID', async () => {
       vi.mocked(apiClient.get).mockResolvedValue({ data: mockPatient });
@@ -5,18 +5,27 @@
  * Handles scheduling, availability checking, and appointment lifecycle management.
  */
 
+import { isAxiosError } from 'axios';
 import { apiClient } from './axios-instance';
 import type {
   Appointment,
   AppointmentListResponse,
   AppointmentSearchFilters,
   AppointmentStatistics,
+  AppointmentType,
   AvailabilityResponse,
+  CalendarFeed,
+  CalendarPrivacyMode,
   CancelAppointmentRequest,
   CreateAppointmentRequest,
+  CreatedCalendarFeed,
+  CreateResourceRequest,
   DailyScheduleResponse,
   MonthlyScheduleResponse,
+  MultiProviderAvailabilityResponse,
+  Resource,
   UpdateAppointmentRequest,
+  UpdateResourceRequest,
   WeeklyScheduleResponse,
 } from '../../types/appointment';
 
@@ -93,28 +102,114 @@ export const appointmentsApi = {
     return response.data;
   },
 
+  /**
+   * Check a patient in (status ARRIVED)
+   * @param id - Appointment UUID
+   * @param arrivedAt - Arrival time (ISO format); now when omitted
+   * @returns The checked-in appointment
+   */
+  checkIn: async (id: string, arrivedAt?: string): Promise<Appointment> => {
+    const response = await apiClient.post<Appointment>(`/api/v1/appointments/${id}/check-in`, {
+      arrived_at: arrivedAt,
+    });
+    return response.data;
+  },
+
   /**
    * Check appointment availability for a provider
    * @param providerId - Provider UUID
    * @param date - Date to check availability (ISO format)
    * @param durationMinutes - Desired appointment duration
+   * @param type - Appointment type, so its buffer times are left free too
+   * @param resourceIds - Rooms and equipment that must be free too
    * @returns Available time slots
    */
   checkAvailability: async (
     providerId: string,
     date: string,
-    durationMinutes: number
+    durationMinutes: number,
+    type?: AppointmentType,
+    resourceIds?: string[]
   ): Promise<AvailabilityResponse> => {
     const response = await apiClient.get<AvailabilityResponse>('/api/v1/appointments/availability', {
       params: {
         provider_id: providerId,
         date,
         duration_minutes: durationMinutes,
+        type,
+        resource_ids: resourceIds?.length ? resourceIds.join(',') : undefined,
       },
     });
     return response.data;
   },
 
+  /**
+   * Search the availability of several providers on a date
+   * @param date - Date to check (ISO 8601)
+   * @param durationMinutes - Desired appointment duration
+   * @param options - Doctors to search (default: all active ones), appointment type,
+   *   resources to reserve and most merged slots to return
+   * @returns Each provider's slots and their free slots merged, earliest first
+   */
+  searchAvailability: async (
+    date: string,
+    durationMinutes: number,
+    options: {
+      providerIds?: string[];
+      type?: AppointmentType;
+      resourceIds?: string[];
+      limit?: number;
+    } = {}
+  ): Promise<MultiProviderAvailabilityResponse> => {
+    const response = await apiClient.get<MultiProviderAvailabilityResponse>(
+      '/api/v1/appointments/availability',
+      {
+        params: {
+          date,
+          duration_minutes: durationMinutes,
+          provider_ids: options.providerIds?.length ? options.providerIds.join(',') : undefined,
+          type: options.type,
+          resource_ids: options.resourceIds?.length ? options.resourceIds.join(',') : undefined,
+          limit: options.limit,
+        },
+      }
+    );
+    return response.data;
+  },
+
+  /**
+   * Get the rooms and equipment appointments can reserve
+   * @param includeInactive - Include deactivated resources
+   * @returns Resources by name
+   */
+  getResources: async (includeInactive = false): Promise<Resource[]> => {
+    const response = await apiClient.get<Resource[]>('/api/v1/appointments/resources', {
+      params: includeInactive ? { include_inactive: true } : undefined,
+    });
+    return response.data;
+  },
+
+  /**
+   * Add a room or piece of equipment (admin only)
+   * @param data - Resource name, type and description
+   * @returns The new resource
+   */
+  createResource: async (data: CreateResourceRequest): Promise<Resource> => {
+    const response = await apiClient.post<Resource>('/api/v1/appointments/resources', data);
+    return response.data;
+  },
+
+  /**
+   * Change or deactivate a resource (admin only)
+   * @param id - Resource UUID
+   * @param data - Fields to change
+   * @returns The updated resource
+   */
+  updateResource: async (id: string, data: UpdateResourceRequest): Promise<Resource> => {
+    const response = await apiClient.put<Resource>(`/api/v1/appointments/resources/${id}`, data);
+    return response.data;
+  },
+
   /**
    * Get appointment statistics
    * @returns Statistics including counts, rates, and distributions
@@ -186,6 +281,54 @@ export const appointmentsApi = {
     return response.data;
   },
 
+  /**
+   * Get the signed-in user's calendar feed
+   * @returns The feed, or null when the user has none
+   */
+  getCalendarFeed: async (): Promise<CalendarFeed | null> => {
+    try {
+      const response = await apiClient.get<CalendarFeed>('/api/v1/appointments/calendar-feed');
+      return response.data;
+    } catch (error) {
+      if (isAxiosError(error) && error.response?.status === 404) {
+        return null;
+      }
+      throw error;
+    }
+  },
+
+  /**
+   * Create the signed-in user's calendar feed; an existing feed gets a new URL
+   * @param privacyMode - Whether events show appointment details or only busy times
+   * @returns The feed with its token and URL path, shown only this once
+   */
+  createCalendarFeed: async (privacyMode: CalendarPrivacyMode): Promise<CreatedCalendarFeed> => {
+    const response = await apiClient.post<CreatedCalendarFeed>(
+      '/api/v1/appointments/calendar-feed',
+      { privacy_mode: privacyMode }
+    );
+    return response.data;
+  },
+
+  /**
+   * Change what the signed-in user's calendar feed shows, keeping its URL
+   * @param privacyMode - Whether events show appointment details or only busy times
+   * @returns The updated feed
+   */
+  updateCalendarFeed: async (privacyMode: CalendarPrivacyMode): Promise<CalendarFeed> => {
+    const response = await apiClient.put<CalendarFeed>('/api/v1/appointments/calendar-feed', {
+      privacy_mode: privacyMode,
+    });
+    return response.data;
+  },
+
+  /**
+   * Revoke the signed-in user's calendar feed
+   */
+  deleteCalendarFeed: async (): Promise<void> => {
+    await apiClient.delete('/api/v1/appointments/calendar-feed');
+  },
+
   /**
    * Get appointments for a date range
    * @param startDate - Start of range (ISO format)
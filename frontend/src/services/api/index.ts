@@ -19,3 +19,4 @@ export * from './audit';
 export * from './system';
 export * from './drug-interactions';
 export * from './notifications';
+export * from './trash';
@@ -13,6 +13,9 @@ import type {
   PatientListResponse,
   PatientSearchFilters,
   PatientStatistics,
+  PatientStatus,
+  PatientChart,
+  PatientChartParams,
 } from '../../types/patient';
 import type {
   PatientInsurance,
@@ -62,6 +65,24 @@ export const patientsApi = {
     return response.data;
   },
 
+  /**
+   * Autocomplete patients by part of their name, fiscal code or phone
+   *
+   * @param params - Query words, optional status and limit (at most 10)
+   * @returns Top matching patients
+   */
+  autocomplete: async (params: {
+    q: string;
+    status?: PatientStatus;
+    limit?: number;
+  }): Promise<PatientListResponse> => {
+    const response = await apiClient.get<PatientListResponse>(
+      '/api/v1/patients/autocomplete',
+      { params }
+    );
+    return response.data;
+  },
+
   /**
    * Get patient by ID
    *
@@ -73,6 +94,25 @@ export const patientsApi = {
     return response.data;
   },
 
+  /**
+   * Get the aggregated patient chart (visits, prescriptions, problems,
+   * appointments, vitals and documents) in one call
+   *
+   * @param id - Patient UUID
+   * @param params - Section sizes
+   * @returns Patient chart
+   */
+  getChart: async (
+    id: string,
+    params?: PatientChartParams
+  ): Promise<PatientChart> => {
+    const response = await apiClient.get<PatientChart>(
+      `/api/v1/patients/${id}/chart`,
+      { params }
+    );
+    return response.data;
+  },
+
   /**
    * Create new patient
    *
@@ -0,0 +1,40 @@
+/**
+ * Recycle Bin API Service
+ *
+ * API methods for listing and restoring deleted patients, draft visits
+ * and prescriptions. Users see the record types they may delete.
+ */
+
+import { apiClient } from './axios-instance';
+import type { TrashFilter, TrashItem, TrashListResponse } from '../../types/trash';
+import { TRASH_RESTORE_PATH } from '../../types/trash';
+
+/**
+ * Recycle bin API endpoints
+ * Base path: /api/v1/trash
+ */
+export const trashApi = {
+  /**
+   * List the recycle bin, most recently deleted first
+   *
+   * @param params - Filter and pagination parameters
+   * @returns Page of deleted records
+   */
+  getAll: async (params?: TrashFilter): Promise<TrashListResponse> => {
+    const response = await apiClient.get<TrashListResponse>('/api/v1/trash', {
+      params,
+    });
+    return response.data;
+  },
+
+  /**
+   * Restore a deleted record
+   *
+   * @param item - Record from the recycle bin
+   */
+  restore: async (item: Pick<TrashItem, 'entity_type' | 'id'>): Promise<void> => {
+    await apiClient.post(
+      `/api/v1/trash/${TRASH_RESTORE_PATH[item.entity_type]}/${item.id}/restore`
+    );
+  },
+};
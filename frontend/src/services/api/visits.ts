@@ -13,6 +13,8 @@ import type {
   SignVisitRequest,
   VisitSearchFilters,
   VisitListResponse,
+  VisitNoteSearchParams,
+  VisitSearchResponse,
   VisitStatistics,
   VisitTemplate,
   CreateVisitTemplateRequest,
@@ -65,6 +67,18 @@ export const visitsApi = {
     return response.data;
   },
 
+  /**
+   * Full-text search over visit notes, with matches highlighted
+   * @param params - Words to find and optional patient filter
+   * @returns Matching visits, best match first
+   */
+  searchNotes: async (params: VisitNoteSearchParams): Promise<VisitSearchResponse> => {
+    const response = await apiClient.get<VisitSearchResponse>('/api/v1/visits/search', {
+      params,
+    });
+    return response.data;
+  },
+
   /**
    * Get a single visit by ID
    * @param id - Visit UUID
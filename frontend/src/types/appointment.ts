@@ -9,8 +9,10 @@
  * Appointment status enum representing the lifecycle of an appointment
  */
 export enum AppointmentStatus {
+  PENDING = 'PENDING',
   SCHEDULED = 'SCHEDULED',
   CONFIRMED = 'CONFIRMED',
+  ARRIVED = 'ARRIVED',
   IN_PROGRESS = 'IN_PROGRESS',
   COMPLETED = 'COMPLETED',
   CANCELLED = 'CANCELLED',
@@ -27,6 +29,7 @@ export enum AppointmentType {
   CONSULTATION = 'CONSULTATION',
   ROUTINE_CHECKUP = 'ROUTINE_CHECKUP',
   ACUPUNCTURE = 'ACUPUNCTURE',
+  TELEVISIT = 'TELEVISIT',
 }
 
 /**
@@ -46,6 +49,8 @@ export function getDefaultDuration(type: AppointmentType): number {
       return 30;
     case AppointmentType.ACUPUNCTURE:
       return 45;
+    case AppointmentType.TELEVISIT:
+      return 30;
     default:
       return 30;
   }
@@ -112,6 +117,7 @@ export interface Appointment {
 
   // Check-in/out
   checked_in_at?: string;
+  visit_started_at?: string;
   checked_out_at?: string;
 
   // Audit
@@ -133,6 +139,54 @@ export interface CreateAppointmentRequest {
   is_recurring?: boolean;
   recurring_pattern?: RecurringPattern;
   send_notification?: boolean; // Send confirmation email to patient
+  /** Book over other appointments, as far as the provider's overbooking policy allows */
+  overbook?: boolean;
+  /** Rooms and equipment to reserve (at most 5) */
+  resource_ids?: string[];
+}
+
+/**
+ * Why an appointment time was refused
+ */
+export type SchedulingConflictKind =
+  | 'OVERLAP'
+  | 'HOLIDAY'
+  | 'NON_WORKING_DAY'
+  | 'OUTSIDE_WORKING_HOURS'
+  | 'BREAK_TIME'
+  | 'BUFFER_TIME'
+  | 'RESOURCE_BUSY';
+
+/**
+ * Reason an appointment time was refused
+ */
+export interface SchedulingConflict {
+  kind: SchedulingConflictKind;
+  message: string;
+  start: string | null; // Blocked time (null for a whole day)
+  end: string | null;
+  appointment_id: string | null; // Overlapping appointment (OVERLAP, BUFFER_TIME, RESOURCE_BUSY)
+  resource_id: string | null; // Reserved resource (RESOURCE_BUSY)
+}
+
+/**
+ * Free time suggested instead of a refused one
+ */
+export interface AlternativeSlot {
+  start: string;
+  end: string;
+}
+
+/**
+ * Body of a 409 SCHEDULING_CONFLICT response
+ */
+export interface SchedulingConflictResponse {
+  error: 'SCHEDULING_CONFLICT';
+  message: string;
+  conflicts: SchedulingConflict[];
+  alternatives: AlternativeSlot[];
+  /** Whether the time can be booked anyway with `overbook: true` */
+  overbookable: boolean;
 }
 
 /**
@@ -147,6 +201,12 @@ export interface UpdateAppointmentRequest {
   status?: AppointmentStatus;
   cancellation_reason?: string;
   send_notification?: boolean; // Send notification on status change (e.g., confirmation)
+  /** updated_at of the record as read; the update fails with 409 if it changed since */
+  expected_version?: string;
+  /** Book over other appointments, as far as the provider's overbooking policy allows */
+  overbook?: boolean;
+  /** Rooms and equipment reserved, replacing the current ones ([] releases them) */
+  resource_ids?: string[];
 }
 
 /**
@@ -164,6 +224,8 @@ export interface AvailabilityRequest {
   provider_id: string;
   date: string; // ISO 8601 format
   duration_minutes: number;
+  type?: AppointmentType; // Also leave its buffer times free
+  resource_ids?: string[]; // Resources that must be free too
 }
 
 /**
@@ -173,6 +235,7 @@ export interface TimeSlot {
   start: string; // ISO 8601 format
   end: string; // ISO 8601 format
   available: boolean;
+  busy_resources?: string[]; // Requested resources reserved during the slot
 }
 
 /**
@@ -182,6 +245,75 @@ export interface AvailabilityResponse {
   date: string;
   provider_id: string;
   slots: TimeSlot[];
+  bottleneck_resource_id?: string | null; // Requested resource reserved in the most free slots
+}
+
+/**
+ * Availability of one provider in a search across providers
+ */
+export interface ProviderAvailability {
+  provider_id: string;
+  provider_name: string;
+  slots: TimeSlot[];
+  bottleneck_resource_id: string | null;
+}
+
+/**
+ * Free slot of one of the providers searched
+ */
+export interface ProviderSlot {
+  provider_id: string;
+  provider_name: string;
+  start: string; // ISO 8601 format
+  end: string; // ISO 8601 format
+}
+
+/**
+ * Availability response when searching several providers
+ */
+export interface MultiProviderAvailabilityResponse {
+  date: string;
+  earliest_slots: ProviderSlot[]; // Free slots of all the providers, earliest first
+  providers: ProviderAvailability[];
+}
+
+/**
+ * Kind of bookable resource
+ */
+export type ResourceType = 'ROOM' | 'EQUIPMENT';
+
+/**
+ * Room or piece of equipment appointments can reserve
+ */
+export interface Resource {
+  id: string;
+  name: string;
+  resource_type: ResourceType;
+  description: string | null;
+  is_active: boolean;
+  created_by: string | null;
+  updated_by: string | null;
+  created_at: string;
+  updated_at: string;
+}
+
+/**
+ * Request to add a resource
+ */
+export interface CreateResourceRequest {
+  name: string;
+  resource_type: ResourceType;
+  description?: string;
+}
+
+/**
+ * Request to change a resource (is_active: false deactivates it)
+ */
+export interface UpdateResourceRequest {
+  name?: string;
+  resource_type?: ResourceType;
+  description?: string;
+  is_active?: boolean;
 }
 
 /**
@@ -219,6 +351,10 @@ export interface AppointmentStatistics {
   upcoming_week: number;
   no_show_rate: number;
   cancellation_rate: number;
+  /** Average minutes from arrival to the start of the visit */
+  avg_wait_minutes: number;
+  /** Average minutes from the start of the visit to its completion */
+  avg_visit_duration_minutes: number;
 }
 
 /**
@@ -280,6 +416,8 @@ export function getStatusColor(status: AppointmentStatus): string {
       return 'bg-blue-100 text-blue-800 dark:bg-blue-900 dark:text-blue-200';
     case AppointmentStatus.CONFIRMED:
       return 'bg-green-100 text-green-800 dark:bg-green-900 dark:text-green-200';
+    case AppointmentStatus.ARRIVED:
+      return 'bg-teal-100 text-teal-800 dark:bg-teal-900 dark:text-teal-200';
     case AppointmentStatus.IN_PROGRESS:
       return 'bg-yellow-100 text-yellow-800 dark:bg-yellow-900 dark:text-yellow-200';
     case AppointmentStatus.COMPLETED:
@@ -310,6 +448,8 @@ export function getTypeColor(type: AppointmentType): string {
       return '#6B7280'; // gray-500
     case AppointmentType.ACUPUNCTURE:
       return '#F59E0B'; // amber-500
+    case AppointmentType.TELEVISIT:
+      return '#06B6D4'; // cyan-500
     default:
       return '#6B7280';
   }
@@ -327,6 +467,7 @@ export function canTransitionStatus(
       return [
         AppointmentStatus.SCHEDULED,
         AppointmentStatus.CONFIRMED,
+        AppointmentStatus.ARRIVED,
         AppointmentStatus.CANCELLED,
         AppointmentStatus.NO_SHOW,
       ].includes(newStatus);
@@ -334,6 +475,16 @@ export function canTransitionStatus(
     case AppointmentStatus.CONFIRMED:
       return [
         AppointmentStatus.CONFIRMED,
+        AppointmentStatus.ARRIVED,
+        AppointmentStatus.IN_PROGRESS,
+        AppointmentStatus.CANCELLED,
+        AppointmentStatus.NO_SHOW,
+      ].includes(newStatus);
+
+    // NO_SHOW when the patient leaves before being seen
+    case AppointmentStatus.ARRIVED:
+      return [
+        AppointmentStatus.ARRIVED,
         AppointmentStatus.IN_PROGRESS,
         AppointmentStatus.CANCELLED,
         AppointmentStatus.NO_SHOW,
@@ -367,3 +518,27 @@ export function isFinalStatus(status: AppointmentStatus): boolean {
     AppointmentStatus.NO_SHOW,
   ].includes(status);
 }
+
+/**
+ * What the events of a calendar feed show
+ */
+export type CalendarPrivacyMode = 'FULL' | 'BUSY_ONLY';
+
+/**
+ * Calendar feed of the signed-in provider (the token is only returned on creation)
+ */
+export interface CalendarFeed {
+  provider_id: string;
+  privacy_mode: CalendarPrivacyMode;
+  last_accessed_at: string | null;
+  created_at: string;
+  updated_at: string;
+}
+
+/**
+ * Newly created calendar feed, with its token and URL path
+ */
+export interface CreatedCalendarFeed extends CalendarFeed {
+  token: string;
+  path: string; // /api/v1/calendar/{token}.ics
+}
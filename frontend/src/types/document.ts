@@ -17,6 +17,7 @@ export enum DocumentType {
   LAB_REQUEST = 'LAB_REQUEST',
   VISIT_SUMMARY = 'VISIT_SUMMARY',
   PRESCRIPTION = 'PRESCRIPTION',
+  SSN_PRESCRIPTION = 'SSN_PRESCRIPTION',
   CUSTOM = 'CUSTOM',
 }
 
@@ -30,6 +31,7 @@ export function getDocumentTypeLabel(type: DocumentType): string {
     [DocumentType.LAB_REQUEST]: 'Lab Request',
     [DocumentType.VISIT_SUMMARY]: 'Visit Summary',
     [DocumentType.PRESCRIPTION]: 'Prescription',
+    [DocumentType.SSN_PRESCRIPTION]: 'SSN Prescription',
     [DocumentType.CUSTOM]: 'Custom Document',
   };
   return labels[type] || type;
@@ -45,6 +47,7 @@ export function getDocumentTypeColor(type: DocumentType): string {
     [DocumentType.LAB_REQUEST]: 'bg-amber-100 text-amber-800 dark:bg-amber-900 dark:text-amber-200',
     [DocumentType.VISIT_SUMMARY]: 'bg-green-100 text-green-800 dark:bg-green-900 dark:text-green-200',
     [DocumentType.PRESCRIPTION]: 'bg-red-100 text-red-800 dark:bg-red-900 dark:text-red-200',
+    [DocumentType.SSN_PRESCRIPTION]: 'bg-rose-100 text-rose-800 dark:bg-rose-900 dark:text-rose-200',
     [DocumentType.CUSTOM]: 'bg-gray-100 text-gray-800 dark:bg-gray-900 dark:text-gray-200',
   };
   return colors[type] || colors[DocumentType.CUSTOM];
@@ -4,6 +4,11 @@
  * All types use snake_case to match backend API responses
  */
 
+import type { Appointment } from './appointment';
+import type { GeneratedDocumentSummary } from './document';
+import type { Prescription } from './prescription';
+import type { Visit } from './visit';
+
 /**
  * Patient status enumeration
  */
@@ -177,6 +182,8 @@ export interface UpdatePatientRequest {
   status?: PatientStatus;
   deceased_date?: string;
   notes?: string;
+  /** updated_at of the record as read; the update fails with 409 if it changed since */
+  expected_version?: string;
 }
 
 /**
@@ -196,6 +203,10 @@ export interface PatientSearchFilters {
   query?: string; // Search query (name, fiscal_code, phone, email)
   status?: PatientStatus;
   gender?: Gender;
+  fiscal_code?: string; // Exact match
+  last_name?: string; // Prefix match
+  phone?: string; // Exact match on primary or secondary phone
+  email?: string; // Exact match
   min_age?: number;
   max_age?: number;
   has_allergies?: boolean;
@@ -223,3 +234,59 @@ export interface PatientStatistics {
   };
   average_age: number;
 }
+
+/**
+ * Problem list entry, as returned in the patient chart
+ */
+export interface PatientChartProblem {
+  id: string;
+  patient_id: string;
+  icd10_code: string;
+  icd10_description: string;
+  status: 'ACTIVE' | 'RESOLVED';
+  onset_date?: string;
+  resolved_date?: string;
+  clinical_notes?: string;
+}
+
+/**
+ * Vital signs of the most recent visit that recorded them
+ */
+export interface PatientChartVitals {
+  visit_id: string;
+  visit_date: string;
+  age_months?: number;
+  weight_kg?: number;
+  height_cm?: number;
+  bmi?: number;
+  blood_pressure_systolic?: number;
+  blood_pressure_diastolic?: number;
+  heart_rate?: number;
+  respiratory_rate?: number;
+  temperature_celsius?: number;
+  oxygen_saturation?: number;
+}
+
+/**
+ * Aggregated patient chart (GET /api/v1/patients/:id/chart)
+ *
+ * Sections the user's role may not read are null.
+ */
+export interface PatientChart {
+  patient: Patient;
+  recent_visits: Visit[] | null;
+  active_prescriptions: Prescription[] | null;
+  problems: PatientChartProblem[] | null;
+  upcoming_appointments: Appointment[] | null;
+  latest_vitals: PatientChartVitals | null;
+  recent_documents: GeneratedDocumentSummary[] | null;
+}
+
+/**
+ * Section sizes of the patient chart (1-50, default 5)
+ */
+export interface PatientChartParams {
+  visits_limit?: number;
+  appointments_limit?: number;
+  documents_limit?: number;
+}
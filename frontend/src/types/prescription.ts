@@ -249,6 +249,25 @@ export interface MedicationSearchResult {
   common_dosages?: string[];
   common_forms?: MedicationForm[];
   default_route?: RouteOfAdministration;
+  /** One of the prescriber's favorites (listed first) */
+  is_favorite?: boolean;
+  /** Times the prescriber has prescribed it */
+  usage_count?: number;
+  /** Default dosing of the favorite */
+  defaults?: MedicationDefaults;
+}
+
+/**
+ * Default dosing of a favorite medication
+ */
+export interface MedicationDefaults {
+  dosage?: string;
+  route?: string;
+  frequency?: string;
+  duration?: string;
+  quantity?: number;
+  refills?: number;
+  instructions?: string;
 }
 
 /**
@@ -25,6 +25,36 @@ export interface ReportDateRange {
   end_date: string;
 }
 
+// ========== PERIOD COMPARISON ==========
+
+/**
+ * Period the summary metrics of a report are compared with
+ */
+export type ComparisonPeriod = 'previous_period' | 'same_period_last_year';
+
+/**
+ * Change of a summary metric from the comparison period
+ */
+export interface MetricChange {
+  current: number;
+  previous: number;
+  /** current - previous (percentage points for rates) */
+  delta: number;
+  /** Relative change in percent (null when the previous value is zero) */
+  percent_change: number | null;
+}
+
+/**
+ * Summary metrics of a report compared with another period
+ */
+export interface PeriodComparison {
+  compare_to: ComparisonPeriod;
+  /** Date range of the comparison period */
+  date_range: ReportDateRange;
+  /** Changes by metric name (the report field of the metric) */
+  metrics: Record<string, MetricChange>;
+}
+
 // ========== APPOINTMENT REPORTS ==========
 
 /**
@@ -37,6 +67,10 @@ export interface AppointmentReportFilter {
   end_date?: string;
   /** Provider ID filter (optional) */
   provider_id?: string;
+  /** Compute from live data instead of the report views (default false) */
+  live?: boolean;
+  /** Compare the summary metrics with another period (requires a date range) */
+  compare_to?: ComparisonPeriod;
 }
 
 /**
@@ -102,6 +136,10 @@ export interface AppointmentUtilizationReport {
   by_hour: HourlyCount[];
   /** Daily trend data */
   daily_trend: DailyAppointmentCount[];
+  /** When the report views it was read from were refreshed (null: computed live) */
+  data_as_of: string | null;
+  /** Summary metrics compared with the `compare_to` period */
+  comparison?: PeriodComparison | null;
 }
 
 // ========== PATIENT REPORTS ==========
@@ -114,6 +152,10 @@ export interface PatientReportFilter {
   start_date?: string;
   /** End date for new patient registration (optional) */
   end_date?: string;
+  /** Compute from live data instead of the report views (default false) */
+  live?: boolean;
+  /** Compare the summary metrics with another period (requires a date range) */
+  compare_to?: ComparisonPeriod;
 }
 
 /**
@@ -134,6 +176,10 @@ export interface AgeGroupCount {
   age_group: string;
   /** Count of patients in group */
   count: number;
+  /** Male patients in group (age pyramid) */
+  male: number;
+  /** Female patients in group (age pyramid) */
+  female: number;
 }
 
 /**
@@ -174,6 +220,10 @@ export interface PatientStatisticsReport {
   age_distribution: AgeGroupCount[];
   /** Monthly registration trend (last 12 months) */
   monthly_registrations: MonthlyCount[];
+  /** When the report views it was read from were refreshed (null: computed live) */
+  data_as_of: string | null;
+  /** Summary metrics compared with the `compare_to` period */
+  comparison?: PeriodComparison | null;
 }
 
 // ========== DIAGNOSIS REPORTS ==========
@@ -190,6 +240,10 @@ export interface DiagnosisReportFilter {
   provider_id?: string;
   /** Limit number of top diagnoses */
   limit?: number;
+  /** Compute from live data instead of the report views (default false) */
+  live?: boolean;
+  /** Compare the summary metrics with another period (requires a date range) */
+  compare_to?: ComparisonPeriod;
 }
 
 /**
@@ -244,6 +298,10 @@ export interface DiagnosisTrendsReport {
   monthly_trend: MonthlyDiagnosisCount[];
   /** Diagnoses by category (ICD-10 chapter) */
   by_category: DiagnosisCategoryCount[];
+  /** When the report views it was read from were refreshed (null: computed live) */
+  data_as_of: string | null;
+  /** Summary metrics compared with the `compare_to` period */
+  comparison?: PeriodComparison | null;
 }
 
 // ========== PROVIDER PRODUCTIVITY REPORTS ==========
@@ -258,6 +316,8 @@ export interface ProductivityReportFilter {
   end_date?: string;
   /** Provider ID filter (optional, if not specified returns all providers) */
   provider_id?: string;
+  /** Compare the summary metrics with another period (requires a date range) */
+  compare_to?: ComparisonPeriod;
 }
 
 /**
@@ -276,6 +336,10 @@ export interface ProductivitySummary {
   total_documents: number;
   /** Average appointment duration (minutes) */
   avg_appointment_duration: number;
+  /** Average wait from arrival to the start of the visit (minutes) */
+  avg_wait_minutes: number;
+  /** Median wait from arrival to the start of the visit (minutes) */
+  median_wait_minutes: number;
 }
 
 /**
@@ -302,6 +366,8 @@ export interface ProviderProductivity {
   avg_visits_per_day: number;
   /** Completion rate */
   completion_rate: number;
+  /** Average wait from arrival to the start of the visit (minutes) */
+  avg_wait_minutes: number;
 }
 
 /**
@@ -314,6 +380,8 @@ export interface ProviderProductivityReport {
   summary: ProductivitySummary;
   /** Per-provider breakdown (if multiple providers) */
   by_provider: ProviderProductivity[];
+  /** Summary metrics compared with the `compare_to` period */
+  comparison?: PeriodComparison | null;
 }
 
 // ========== REVENUE REPORTS ==========
@@ -328,6 +396,8 @@ export interface RevenueReportFilter {
   end_date?: string;
   /** Provider ID filter (optional) */
   provider_id?: string;
+  /** Compare the summary metrics with another period (requires a date range) */
+  compare_to?: ComparisonPeriod;
 }
 
 /**
@@ -344,6 +414,8 @@ export interface RevenueReport {
   avg_visits_per_day: number;
   /** Note about billing integration requirement */
   note: string;
+  /** Summary metrics compared with the `compare_to` period */
+  comparison?: PeriodComparison | null;
 }
 
 // ========== DASHBOARD REPORT ==========
@@ -411,6 +483,30 @@ export interface RecentActivity {
   new_patients: NewPatientSummary[];
 }
 
+/**
+ * Post-visit survey results over the last `period_days` days
+ */
+export interface SatisfactionSummary {
+  /** Days covered, up to today */
+  period_days: number;
+  /** Surveys sent */
+  surveys_sent: number;
+  /** Surveys answered */
+  responses: number;
+  /** Percentage of surveys answered */
+  response_rate: number;
+  /** Average score (0-10); null without responses */
+  average_score: number | null;
+  /** Responses scoring 9 or 10 */
+  promoters: number;
+  /** Responses scoring 7 or 8 */
+  passives: number;
+  /** Responses scoring 0 to 6 */
+  detractors: number;
+  /** Net Promoter Score (-100 to 100); null without responses */
+  nps: number | null;
+}
+
 /**
  * Dashboard overview report
  */
@@ -421,6 +517,8 @@ export interface DashboardReport {
   quick_stats: QuickStats;
   /** Recent activity */
   recent_activity: RecentActivity;
+  /** Post-visit survey results */
+  satisfaction: SatisfactionSummary;
 }
 
 // ========== EXPORT ==========
@@ -460,6 +558,10 @@ export interface ExportReportRequest {
   end_date?: string;
   /** Provider filter */
   provider_id?: string;
+  /** Compute from live data instead of the report views (default false) */
+  live?: boolean;
+  /** Compare the summary metrics with another period (requires a date range) */
+  compare_to?: ComparisonPeriod;
 }
 
 // ========== HELPER FUNCTIONS ==========
@@ -0,0 +1,61 @@
+/**
+ * Recycle Bin Type Definitions
+ *
+ * Type definitions for deleted records awaiting restore or purge.
+ * These types align with the backend API in handlers/trash.rs
+ * and models/trash.rs
+ */
+
+/**
+ * Kind of record in the recycle bin
+ * Matches TrashEntityType enum in backend
+ */
+export type TrashEntityType = 'PATIENT' | 'VISIT' | 'PRESCRIPTION';
+
+/**
+ * Path segment of the restore endpoint for each kind of record
+ */
+export const TRASH_RESTORE_PATH: Record<TrashEntityType, string> = {
+  PATIENT: 'patients',
+  VISIT: 'visits',
+  PRESCRIPTION: 'prescriptions',
+};
+
+/**
+ * Record in the recycle bin
+ * Matches TrashItem in backend
+ */
+export interface TrashItem {
+  entity_type: TrashEntityType;
+  id: string;
+  /** The patient itself for a deleted patient */
+  patient_id: string;
+  patient_name: string | null;
+  /** Visit type and date, or medication name */
+  summary: string | null;
+  deleted_at: string;
+  deleted_by: string | null;
+  deleted_by_name: string | null;
+  /** Null while the RECYCLE_BIN retention rule is disabled or in dry-run mode */
+  purge_after: string | null;
+}
+
+/**
+ * Query parameters for listing the recycle bin
+ */
+export interface TrashFilter {
+  entity_type?: TrashEntityType;
+  limit?: number;
+  offset?: number;
+}
+
+/**
+ * Recycle bin listing
+ * Matches TrashListResponse in backend
+ */
+export interface TrashListResponse {
+  items: TrashItem[];
+  total: number;
+  limit: number;
+  offset: number;
+}
@@ -19,6 +19,7 @@
  */
 export enum VisitStatus {
   DRAFT = 'DRAFT',
+  PENDING_COSIGN = 'PENDING_COSIGN',
   SIGNED = 'SIGNED',
   LOCKED = 'LOCKED',
 }
@@ -164,14 +165,63 @@ export interface Visit {
   signed_by?: string;
   signed_by_name?: string;
 
+  // Co-signature
+  cosign_supervisor_id?: string;
+  cosigned_by?: string;
+  cosigned_at?: string;
+  cosign_return_note?: string;
+
   // Locking
   locked_at?: string;
 
+  // Photos, audio memos and files (only when fetching a single visit)
+  attachments?: VisitAttachment[];
+
   // Audit
   created_at: string;
   updated_at: string;
 }
 
+/**
+ * Kind of visit attachment, from the uploaded file's type
+ */
+export type VisitAttachmentKind = 'PHOTO' | 'AUDIO' | 'FILE';
+
+/**
+ * Progress of an audio memo's transcription
+ */
+export type TranscriptionStatus = 'PENDING' | 'PROCESSING' | 'COMPLETED' | 'FAILED' | 'SKIPPED';
+
+/**
+ * Transcript split into text proposed for the visit note, for review
+ */
+export interface TranscriptProposal {
+  subjective?: string;
+  objective?: string;
+}
+
+/**
+ * Clinical photo, audio memo or file attached to a visit
+ */
+export interface VisitAttachment {
+  id: string;
+  visit_id: string;
+  patient_id: string;
+  kind: VisitAttachmentKind;
+  caption?: string;
+  body_site?: string;
+  original_filename: string;
+  mime_type: string;
+  file_size_bytes: number;
+  // Audio memos only
+  transcription_status?: TranscriptionStatus;
+  transcript?: string;
+  transcript_proposal?: TranscriptProposal;
+  created_by: string;
+  created_at: string;
+  download_url: string;
+}
+
 /**
  * Request to create a new visit
  */
@@ -202,6 +252,8 @@ export interface UpdateVisitRequest {
   plan?: string;
   additional_notes?: string;
   follow_up_instructions?: string;
+  /** updated_at of the record as read; the update fails with 409 if it changed since */
+  expected_version?: string;
 }
 
 /**
@@ -236,6 +288,51 @@ export interface VisitListResponse {
   offset: number;
 }
 
+/**
+ * Full-text search over visit notes
+ */
+export interface VisitNoteSearchParams {
+  q: string;
+  patient_id?: string;
+  limit?: number;
+  offset?: number;
+}
+
+/**
+ * Matching passage of a visit field; `snippet` is HTML-escaped with the
+ * matching words wrapped in <mark>
+ */
+export interface VisitSearchHighlight {
+  field: string;
+  snippet: string;
+}
+
+/**
+ * Visit found by a note search
+ */
+export interface VisitSearchHit {
+  visit_id: string;
+  patient_id: string;
+  patient_name?: string;
+  provider_id: string;
+  provider_name?: string;
+  visit_date: string;
+  visit_type: VisitType;
+  status: VisitStatus;
+  rank: number;
+  highlights: VisitSearchHighlight[];
+}
+
+/**
+ * Visit note search results
+ */
+export interface VisitSearchResponse {
+  results: VisitSearchHit[];
+  total: number;
+  limit: number;
+  offset: number;
+}
+
 /**
  * Visit statistics
  */
@@ -300,7 +397,9 @@ export interface VisitVersion {
 export function canTransitionStatus(currentStatus: VisitStatus, newStatus: VisitStatus): boolean {
   switch (currentStatus) {
     case VisitStatus.DRAFT:
-      return [VisitStatus.DRAFT, VisitStatus.SIGNED].includes(newStatus);
+      return [VisitStatus.DRAFT, VisitStatus.SIGNED, VisitStatus.PENDING_COSIGN].includes(newStatus);
+    case VisitStatus.PENDING_COSIGN:
+      return [VisitStatus.PENDING_COSIGN, VisitStatus.SIGNED, VisitStatus.DRAFT].includes(newStatus);
     case VisitStatus.SIGNED:
       return [VisitStatus.SIGNED, VisitStatus.LOCKED].includes(newStatus);
     case VisitStatus.LOCKED: